@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use align_ext::AlignExt;
+use aster_trace::{declare_tracepoint, tracepoint};
 use bitvec::array::BitArray;
 use int_to_c_enum::TryFromInt;
 use ostd::{
@@ -13,16 +14,28 @@ use ostd::{
 };
 use spin::Once;
 
-use super::{id::Sid, BlockDevice};
+use super::{
+    id::Sid,
+    ioprio::{self, IoPriority},
+    BlockDevice,
+};
 use crate::{prelude::*, BLOCK_SIZE, SECTOR_SIZE};
 
+declare_tracepoint! {
+    /// Emitted when a `Bio` is submitted to a block device.
+    pub(crate) static BIO_SUBMIT: block:bio_submit(sector, nr_sectors, type_);
+    /// Emitted when a `Bio` is completed, with its final `BioStatus`.
+    pub(crate) static BIO_COMPLETE: block:bio_complete(sector, nr_sectors, type_, status);
+}
+
 /// The unit for block I/O.
 ///
 /// Each `Bio` packs the following information:
 /// (1) The type of the I/O,
 /// (2) The target sectors on the device for doing I/O,
 /// (3) The memory locations (`BioSegment`) from/to which data are read/written,
-/// (4) The optional callback function that will be invoked when the I/O is completed.
+/// (4) The optional callback function that will be invoked when the I/O is completed,
+/// (5) The I/O priority of the task that creates the `Bio`.
 #[derive(Debug)]
 pub struct Bio(Arc<BioInner>);
 
@@ -44,13 +57,37 @@ impl Bio {
             .map(|segment| segment.nsectors().to_raw())
             .sum();
 
+        Self::new_inner(
+            type_,
+            start_sid..start_sid + nsectors,
+            segments,
+            complete_fn,
+        )
+    }
+
+    /// Constructs a new `Bio` that discards the sectors in `sid_range`.
+    ///
+    /// The discarded sectors have unspecified contents afterwards. The `complete_fn` is the
+    /// optional callback function.
+    pub fn new_discard(sid_range: Range<Sid>, complete_fn: Option<fn(&SubmittedBio)>) -> Self {
+        Self::new_inner(BioType::Discard, sid_range, Vec::new(), complete_fn)
+    }
+
+    fn new_inner(
+        type_: BioType,
+        sid_range: Range<Sid>,
+        segments: Vec<BioSegment>,
+        complete_fn: Option<fn(&SubmittedBio)>,
+    ) -> Self {
         let inner = Arc::new(BioInner {
             type_,
-            sid_range: start_sid..start_sid + nsectors,
+            sid_range,
             segments,
             complete_fn,
+            priority: ioprio::current_priority(),
             status: AtomicU32::new(BioStatus::Init as u32),
             wait_queue: WaitQueue::new(),
+            origin: None,
         });
         Self(inner)
     }
@@ -70,6 +107,11 @@ impl Bio {
         self.0.segments()
     }
 
+    /// Returns the I/O priority.
+    pub fn priority(&self) -> IoPriority {
+        self.0.priority()
+    }
+
     /// Returns the status.
     pub fn status(&self) -> BioStatus {
         self.0.status()
@@ -92,6 +134,12 @@ impl Bio {
         );
         assert!(result.is_ok());
 
+        tracepoint!(
+            BIO_SUBMIT,
+            self.0.sid_range.start.to_raw(),
+            self.0.sid_range.end.to_raw() - self.0.sid_range.start.to_raw(),
+            self.0.type_ as u8
+        );
         if let Err(e) = block_device.enqueue(SubmittedBio(self.0.clone())) {
             // Fail to submit, revert the status.
             let result = self.0.status.compare_exchange(
@@ -261,6 +309,11 @@ impl SubmittedBio {
         self.0.segments()
     }
 
+    /// Returns the I/O priority.
+    pub fn priority(&self) -> IoPriority {
+        self.0.priority()
+    }
+
     /// Returns the status.
     pub fn status(&self) -> BioStatus {
         self.0.status()
@@ -281,10 +334,158 @@ impl SubmittedBio {
         );
         assert!(result.is_ok());
 
+        tracepoint!(
+            BIO_COMPLETE,
+            self.0.sid_range.start.to_raw(),
+            self.0.sid_range.end.to_raw() - self.0.sid_range.start.to_raw(),
+            self.0.type_ as u8,
+            status as u32
+        );
         self.0.wait_queue.wake_all();
         if let Some(complete_fn) = self.0.complete_fn {
             complete_fn(self);
         }
+        if let Some(origin) = self.0.origin.as_ref() {
+            origin.complete_part(status);
+        }
+    }
+
+    /// Submits a copy of the `Bio` to the `block_device`, with the target sectors shifted by
+    /// `sid_offset`.
+    ///
+    /// This allows a block device that is a part of another block device (e.g., a partition) to
+    /// forward the requests. The `Bio` is completed with the same status when the copy is
+    /// completed.
+    pub fn remap_and_submit(
+        self,
+        block_device: &dyn BlockDevice,
+        sid_offset: u64,
+    ) -> Result<(), BioEnqueueError> {
+        let part = BioPart {
+            block_device,
+            sid_range: self.sid_range().clone(),
+            target_sid: self.sid_range().start + sid_offset,
+        };
+        self.split_and_submit(&[part])
+    }
+
+    /// Submits the parts of the `Bio` as new `Bio`s to the block devices.
+    ///
+    /// This allows a block device that is composed of other block devices (e.g., a striped
+    /// device) to forward the requests. The `Bio` is completed after all the parts are completed.
+    /// It is completed with the status of the first failed part if any part fails.
+    ///
+    /// If the first part cannot be submitted, the error is returned and the `Bio` is not
+    /// completed. If any other part cannot be submitted, the remaining parts are treated as
+    /// failed, since the submitted parts cannot be revoked.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `parts` is empty or the sectors of any part are out of the
+    /// sectors of the `Bio`.
+    pub fn split_and_submit(self, parts: &[BioPart]) -> Result<(), BioEnqueueError> {
+        assert!(!parts.is_empty());
+
+        let origin = Arc::new(BioOrigin {
+            nr_pending: AtomicUsize::new(parts.len()),
+            status: AtomicU32::new(BioStatus::Complete as u32),
+            bio: self,
+        });
+
+        for (i, part) in parts.iter().enumerate() {
+            let inner = Arc::new(BioInner {
+                type_: origin.bio.type_(),
+                sid_range: part.target_sid
+                    ..part.target_sid
+                        + (part.sid_range.end.to_raw() - part.sid_range.start.to_raw()),
+                segments: origin.bio.slice_segments(&part.sid_range),
+                complete_fn: None,
+                priority: origin.bio.priority(),
+                status: AtomicU32::new(BioStatus::Init as u32),
+                wait_queue: WaitQueue::new(),
+                origin: Some(origin.clone()),
+            });
+
+            // The parts are waited through the original `Bio`.
+            if let Err(err) = Bio(inner).submit(part.block_device) {
+                if i == 0 {
+                    return Err(err);
+                }
+                for _ in i..parts.len() {
+                    origin.complete_part(BioStatus::IoError);
+                }
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the memory segments that correspond to the sectors in `sid_range`.
+    fn slice_segments(&self, sid_range: &Range<Sid>) -> Vec<BioSegment> {
+        let bio_range = self.sid_range();
+        assert!(bio_range.start <= sid_range.start && sid_range.end <= bio_range.end);
+
+        // The bios without memory segments (e.g., the discard bios) are split by sectors only.
+        if self.segments().is_empty() || sid_range == bio_range {
+            return self.segments().to_vec();
+        }
+
+        let start = (sid_range.start.to_raw() - bio_range.start.to_raw()) as usize * SECTOR_SIZE;
+        let end = (sid_range.end.to_raw() - bio_range.start.to_raw()) as usize * SECTOR_SIZE;
+
+        let mut segments = Vec::new();
+        let mut segment_start = 0;
+        for segment in self.segments() {
+            let segment_end = segment_start + segment.nbytes();
+            if segment_start < end && start < segment_end {
+                let slice_start = start.max(segment_start) - segment_start;
+                let slice_end = end.min(segment_end) - segment_start;
+                segments.push(segment.slice(slice_start..slice_end));
+            }
+            segment_start = segment_end;
+        }
+        segments
+    }
+}
+
+/// A part of a `Bio` that is submitted to a block device.
+///
+/// See [`SubmittedBio::split_and_submit`].
+#[derive(Debug)]
+pub struct BioPart<'a> {
+    /// The block device to which the part is submitted.
+    pub block_device: &'a dyn BlockDevice,
+    /// The sectors of the part in the original `Bio`.
+    pub sid_range: Range<Sid>,
+    /// The first target sector on the block device.
+    pub target_sid: Sid,
+}
+
+/// The original `Bio` whose parts are submitted as new `Bio`s.
+struct BioOrigin {
+    bio: SubmittedBio,
+    /// The number of the parts that are not completed
+    nr_pending: AtomicUsize,
+    /// The status of the first failed part, or `BioStatus::Complete`
+    status: AtomicU32,
+}
+
+impl BioOrigin {
+    fn complete_part(&self, status: BioStatus) {
+        if status != BioStatus::Complete {
+            let _ = self.status.compare_exchange(
+                BioStatus::Complete as u32,
+                status as u32,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            );
+        }
+
+        if self.nr_pending.fetch_sub(1, Ordering::AcqRel) == 1 {
+            let status = BioStatus::try_from(self.status.load(Ordering::Relaxed)).unwrap();
+            self.bio.complete(status);
+        }
     }
 }
 
@@ -298,10 +499,14 @@ struct BioInner {
     segments: Vec<BioSegment>,
     /// The I/O completion method
     complete_fn: Option<fn(&SubmittedBio)>,
+    /// The I/O priority
+    priority: IoPriority,
     /// The I/O status
     status: AtomicU32,
     /// The wait queue for I/O completion
     wait_queue: WaitQueue,
+    /// The original `Bio`, if this one is a part of it
+    origin: Option<Arc<BioOrigin>>,
 }
 
 impl BioInner {
@@ -317,6 +522,10 @@ impl BioInner {
         &self.segments
     }
 
+    pub fn priority(&self) -> IoPriority {
+        self.priority
+    }
+
     pub fn status(&self) -> BioStatus {
         BioStatus::try_from(self.status.load(Ordering::Relaxed)).unwrap()
     }
@@ -329,6 +538,7 @@ impl Debug for BioInner {
             .field("sid_range", &self.sid_range())
             .field("status", &self.status())
             .field("segments", &self.segments())
+            .field("priority", &self.priority())
             .field("complete_fn", &self.complete_fn)
             .finish()
     }
@@ -474,6 +684,33 @@ impl<'a> BioSegment {
         self.inner.dma_slice.offset() % BLOCK_SIZE
     }
 
+    /// Returns a new `BioSegment` that refers to the bytes in `range` of this one.
+    ///
+    /// # Panics
+    ///
+    /// If the `range` is not sector aligned or out of bounds, this method will panic.
+    pub fn slice(&self, range: Range<usize>) -> Self {
+        assert!(
+            is_sector_aligned(range.start)
+                && is_sector_aligned(range.end)
+                && range.start <= range.end
+                && range.end <= self.nbytes()
+        );
+
+        let dma_slice = &self.inner.dma_slice;
+        Self {
+            inner: Arc::new(BioSegmentInner {
+                dma_slice: DmaStreamSlice::new(
+                    dma_slice.stream().clone(),
+                    dma_slice.offset() + range.start,
+                    range.len(),
+                ),
+                // The memory is returned to the pool by the original segment.
+                from_pool: false,
+            }),
+        }
+    }
+
     /// Returns the inner DMA slice.
     pub fn inner_dma_slice(&self) -> &DmaStreamSlice<DmaStream> {
         &self.inner.dma_slice
@@ -0,0 +1,381 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The mapped block devices, which are composed of other block devices like the device mapper of
+//! Linux.
+//!
+//! A [`MappedDevice`] is described by a [`DmTable`], which consists of [`DmTarget`]s that map the
+//! consecutive ranges of its sectors. Each target maps its sectors to the underlying block
+//! devices in its own way, e.g., [`Linear`] concatenates the devices and [`Striped`] distributes
+//! the chunks of sectors across the devices in a round-robin way.
+//!
+//! A device is created without any table. A table is loaded first and then activated by
+//! [`MappedDevice::swap_table`], so the table can be replaced while the device is in use.
+
+use ostd::sync::SpinLock;
+
+use crate::{
+    bio::{BioEnqueueError, BioPart, BioStatus, BioType, SubmittedBio},
+    id::Sid,
+    prelude::*,
+    BlockDevice, BlockDeviceMeta,
+};
+
+/// A block device that maps its sectors to other block devices.
+#[derive(Debug)]
+pub struct MappedDevice {
+    /// The active table, or `None` if no table has been activated
+    table: SpinLock<Option<Arc<DmTable>>>,
+    read_only: bool,
+}
+
+impl MappedDevice {
+    /// Creates a device without a table, whose size is zero until a table is activated.
+    pub fn new(read_only: bool) -> Self {
+        Self {
+            table: SpinLock::new(None),
+            read_only,
+        }
+    }
+
+    /// Returns the active table.
+    pub fn table(&self) -> Option<Arc<DmTable>> {
+        self.table.lock().clone()
+    }
+
+    /// Activates the table and returns the previous one.
+    ///
+    /// The requests that have been forwarded with the previous table are not affected.
+    pub fn swap_table(&self, table: Arc<DmTable>) -> Option<Arc<DmTable>> {
+        self.table.lock().replace(table)
+    }
+
+    /// Returns whether the writes to the device are rejected.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+}
+
+impl BlockDevice for MappedDevice {
+    fn enqueue(&self, bio: SubmittedBio) -> Result<(), BioEnqueueError> {
+        let Some(table) = self.table() else {
+            bio.complete(BioStatus::IoError);
+            return Ok(());
+        };
+
+        let is_write = matches!(bio.type_(), BioType::Write | BioType::Discard);
+        if (is_write && self.read_only) || bio.sid_range().end.to_raw() > table.nr_sectors() {
+            bio.complete(BioStatus::IoError);
+            return Ok(());
+        }
+
+        let mut pieces = Vec::new();
+        if bio.type_() == BioType::Flush {
+            // The flush requests have no sectors, so they are sent to all the devices.
+            for device in table.devices() {
+                pieces.push(TargetPiece {
+                    device,
+                    sid_range: bio.sid_range().clone(),
+                    target_sid: Sid::new(0),
+                });
+            }
+        } else {
+            table.map(bio.sid_range(), &mut pieces);
+        }
+        if pieces.is_empty() {
+            bio.complete(BioStatus::Complete);
+            return Ok(());
+        }
+
+        let parts = pieces
+            .iter()
+            .map(|piece| BioPart {
+                block_device: piece.device.as_ref(),
+                sid_range: piece.sid_range.clone(),
+                target_sid: piece.target_sid,
+            })
+            .collect::<Vec<_>>();
+        bio.split_and_submit(&parts)
+    }
+
+    fn metadata(&self) -> BlockDeviceMeta {
+        let Some(table) = self.table() else {
+            return BlockDeviceMeta {
+                max_nr_segments_per_bio: 1,
+                nr_sectors: 0,
+                max_nr_sectors_per_discard: 0,
+            };
+        };
+
+        let max_nr_segments_per_bio = table
+            .devices()
+            .iter()
+            .map(|device| device.metadata().max_nr_segments_per_bio)
+            .min()
+            .unwrap_or(1);
+        BlockDeviceMeta {
+            max_nr_segments_per_bio,
+            nr_sectors: table.nr_sectors() as usize,
+            // The discard requests are forwarded, but the limits of the devices are not tracked.
+            max_nr_sectors_per_discard: 0,
+        }
+    }
+}
+
+/// The table of a mapped device.
+#[derive(Debug)]
+pub struct DmTable {
+    targets: Vec<DmTarget>,
+}
+
+impl DmTable {
+    /// Creates a table from the targets, which must map consecutive sectors from zero.
+    pub fn new(targets: Vec<DmTarget>) -> Result<Self, DmError> {
+        if targets.is_empty() {
+            return Err(DmError::InvalidTable);
+        }
+
+        let mut next_start = 0;
+        for target in targets.iter() {
+            if target.start != next_start {
+                return Err(DmError::InvalidTable);
+            }
+            next_start = target.start + target.len;
+        }
+
+        Ok(Self { targets })
+    }
+
+    /// Returns the targets.
+    pub fn targets(&self) -> &[DmTarget] {
+        &self.targets
+    }
+
+    /// Returns the total number of sectors mapped by the table.
+    pub fn nr_sectors(&self) -> u64 {
+        let last = self.targets.last().unwrap();
+        last.start + last.len
+    }
+
+    /// Returns the underlying devices without duplicates.
+    fn devices(&self) -> Vec<Arc<dyn BlockDevice>> {
+        let mut devices: Vec<Arc<dyn BlockDevice>> = Vec::new();
+        for target in self.targets.iter() {
+            for device in target.kind.devices() {
+                if !devices.iter().any(|other| Arc::ptr_eq(other, &device)) {
+                    devices.push(device);
+                }
+            }
+        }
+        devices
+    }
+
+    fn map(&self, sid_range: &Range<Sid>, pieces: &mut Vec<TargetPiece>) {
+        let start = sid_range.start.to_raw();
+        let end = sid_range.end.to_raw();
+
+        for target in self.targets.iter() {
+            let target_end = target.start + target.len;
+            if target_end <= start || end <= target.start {
+                continue;
+            }
+
+            let first = pieces.len();
+            let range = start.max(target.start) - target.start..end.min(target_end) - target.start;
+            target.kind.map(range, pieces);
+            // The sectors of the pieces are relative to the target.
+            for piece in pieces[first..].iter_mut() {
+                piece.sid_range =
+                    piece.sid_range.start + target.start..piece.sid_range.end + target.start;
+            }
+        }
+    }
+}
+
+/// A target in the table, which maps a range of the sectors of the mapped device.
+#[derive(Debug)]
+pub struct DmTarget {
+    start: u64,
+    len: u64,
+    kind: Box<dyn Target>,
+}
+
+impl DmTarget {
+    /// Creates a target that maps `len` sectors starting from the sector `start`.
+    pub fn new(start: u64, len: u64, kind: Box<dyn Target>) -> Result<Self, DmError> {
+        if len == 0 || start.checked_add(len).is_none() {
+            return Err(DmError::InvalidTable);
+        }
+
+        Ok(Self { start, len, kind })
+    }
+
+    /// Returns the first sector mapped by the target.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Returns the number of sectors mapped by the target.
+    pub fn nr_sectors(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns the type of the target, e.g., `linear`.
+    pub fn type_name(&self) -> &'static str {
+        self.kind.type_name()
+    }
+}
+
+/// The way how a target maps its sectors to the underlying devices.
+pub trait Target: Send + Sync + Debug {
+    /// Returns the name of the target type, which is the same as Linux.
+    fn type_name(&self) -> &'static str;
+
+    /// Returns the underlying devices.
+    fn devices(&self) -> Vec<Arc<dyn BlockDevice>>;
+
+    /// Maps the sectors in `sid_range` of the target to the pieces on the underlying devices.
+    ///
+    /// The sectors of the pieces are relative to the start of the target.
+    fn map(&self, sid_range: Range<u64>, pieces: &mut Vec<TargetPiece>);
+}
+
+/// Consecutive sectors of a target that are mapped to consecutive sectors of a device.
+#[derive(Debug)]
+pub struct TargetPiece {
+    /// The underlying device.
+    pub device: Arc<dyn BlockDevice>,
+    /// The sectors of the target.
+    pub sid_range: Range<Sid>,
+    /// The first sector on the device.
+    pub target_sid: Sid,
+}
+
+/// The target that maps its sectors to the consecutive sectors of a device, i.e., the `linear`
+/// target.
+#[derive(Debug)]
+pub struct Linear {
+    device: Arc<dyn BlockDevice>,
+    start: u64,
+}
+
+impl Linear {
+    /// Creates a target of `len` sectors that starts from the sector `start` of the device.
+    pub fn new(device: Arc<dyn BlockDevice>, start: u64, len: u64) -> Result<Self, DmError> {
+        check_device_size(device.as_ref(), start, len)?;
+        Ok(Self { device, start })
+    }
+}
+
+impl Target for Linear {
+    fn type_name(&self) -> &'static str {
+        "linear"
+    }
+
+    fn devices(&self) -> Vec<Arc<dyn BlockDevice>> {
+        vec![self.device.clone()]
+    }
+
+    fn map(&self, sid_range: Range<u64>, pieces: &mut Vec<TargetPiece>) {
+        pieces.push(TargetPiece {
+            device: self.device.clone(),
+            sid_range: Sid::new(sid_range.start)..Sid::new(sid_range.end),
+            target_sid: Sid::new(self.start + sid_range.start),
+        });
+    }
+}
+
+/// The target that distributes the chunks of its sectors across the devices in a round-robin
+/// way, i.e., the `striped` target.
+#[derive(Debug)]
+pub struct Striped {
+    /// The devices and their first sectors
+    stripes: Vec<(Arc<dyn BlockDevice>, u64)>,
+    chunk_sectors: u64,
+}
+
+impl Striped {
+    /// Creates a target of `len` sectors whose chunks of `chunk_sectors` sectors are distributed
+    /// across the stripes, i.e., the devices and their first sectors.
+    ///
+    /// Like Linux, the size of a chunk must be a power of two and at least a page, and the number
+    /// of sectors must be a multiple of the number of stripes.
+    pub fn new(
+        stripes: Vec<(Arc<dyn BlockDevice>, u64)>,
+        chunk_sectors: u64,
+        len: u64,
+    ) -> Result<Self, DmError> {
+        const MIN_CHUNK_SECTORS: u64 = (crate::BLOCK_SIZE / crate::SECTOR_SIZE) as u64;
+
+        if stripes.is_empty()
+            || !chunk_sectors.is_power_of_two()
+            || chunk_sectors < MIN_CHUNK_SECTORS
+            || len % stripes.len() as u64 != 0
+        {
+            return Err(DmError::InvalidArgs);
+        }
+
+        let stripe_len = len / stripes.len() as u64;
+        for (device, start) in stripes.iter() {
+            check_device_size(device.as_ref(), *start, stripe_len)?;
+        }
+
+        Ok(Self {
+            stripes,
+            chunk_sectors,
+        })
+    }
+}
+
+impl Target for Striped {
+    fn type_name(&self) -> &'static str {
+        "striped"
+    }
+
+    fn devices(&self) -> Vec<Arc<dyn BlockDevice>> {
+        self.stripes
+            .iter()
+            .map(|(device, _)| device.clone())
+            .collect()
+    }
+
+    fn map(&self, sid_range: Range<u64>, pieces: &mut Vec<TargetPiece>) {
+        let nr_stripes = self.stripes.len() as u64;
+
+        let mut sid = sid_range.start;
+        while sid < sid_range.end {
+            let chunk = sid / self.chunk_sectors;
+            let offset = sid % self.chunk_sectors;
+            let end = sid_range.end.min((chunk + 1) * self.chunk_sectors);
+
+            let (device, start) = &self.stripes[(chunk % nr_stripes) as usize];
+            pieces.push(TargetPiece {
+                device: device.clone(),
+                sid_range: Sid::new(sid)..Sid::new(end),
+                target_sid: Sid::new(start + chunk / nr_stripes * self.chunk_sectors + offset),
+            });
+
+            sid = end;
+        }
+    }
+}
+
+/// Checks that the device has `len` sectors starting from the sector `start`.
+fn check_device_size(device: &dyn BlockDevice, start: u64, len: u64) -> Result<(), DmError> {
+    let nr_sectors = device.metadata().nr_sectors as u64;
+    if start.checked_add(len).is_none_or(|end| end > nr_sectors) {
+        return Err(DmError::DeviceTooSmall);
+    }
+
+    Ok(())
+}
+
+/// The errors of the mapped devices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DmError {
+    /// The targets are empty, overlapped, or not consecutive.
+    InvalidTable,
+    /// The arguments of the target are invalid.
+    InvalidArgs,
+    /// The sectors of the target are beyond the end of the device.
+    DeviceTooSmall,
+}
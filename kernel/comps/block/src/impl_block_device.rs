@@ -89,6 +89,57 @@ impl dyn BlockDevice {
         let status = bio.submit_and_wait(self)?;
         Ok(status)
     }
+
+    /// Asynchronously issues a sync request.
+    ///
+    /// The sync request completes after all the writes completed before it reach the
+    /// persistent storage.
+    pub fn sync_async(&self) -> Result<BioWaiter, BioEnqueueError> {
+        let bio = Bio::new(
+            BioType::Flush,
+            Sid::from(Bid::from_offset(0)),
+            vec![],
+            Some(general_complete_fn),
+        );
+        bio.submit(self)
+    }
+
+    /// Synchronously discards the blocks in `bid_range`.
+    pub fn discard_blocks(&self, bid_range: Range<Bid>) -> Result<BioStatus, BioEnqueueError> {
+        let waiter = self.discard_blocks_async(bid_range)?;
+        match waiter.wait() {
+            Some(status) => Ok(status),
+            None => Ok((0..waiter.nreqs())
+                .map(|i| waiter.status(i))
+                .find(|status| *status != BioStatus::Complete)
+                .unwrap()),
+        }
+    }
+
+    /// Asynchronously discards the blocks in `bid_range`.
+    ///
+    /// The range is split into multiple bios if it exceeds the limit of the block device.
+    pub fn discard_blocks_async(
+        &self,
+        bid_range: Range<Bid>,
+    ) -> Result<BioWaiter, BioEnqueueError> {
+        let mut sid = Sid::from(bid_range.start);
+        let end_sid = Sid::from(bid_range.end);
+        // A device without the discard support completes the bio with an error.
+        let max_nr_sectors = match self.metadata().max_nr_sectors_per_discard {
+            0 => usize::MAX,
+            max => max,
+        } as u64;
+
+        let mut bio_waiter = BioWaiter::new();
+        while sid < end_sid {
+            let next_sid = sid + (end_sid.to_raw() - sid.to_raw()).min(max_nr_sectors);
+            let bio = Bio::new_discard(sid..next_sid, Some(general_complete_fn));
+            bio_waiter.concat(bio.submit(self)?);
+            sid = next_sid;
+        }
+        Ok(bio_waiter)
+    }
 }
 
 impl VmIo for dyn BlockDevice {
@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The I/O priorities of the bios.
+//!
+//! Each `Bio` carries the I/O priority of the task that creates it, which is used by the request
+//! queues to decide which request is dispatched to the device first. The I/O priority of the
+//! current task is provided by the function registered with [`register_current_priority_fn`].
+
+use int_to_c_enum::TryFromInt;
+use spin::Once;
+
+/// The I/O scheduling class.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromInt)]
+#[repr(u8)]
+pub enum IoPrioClass {
+    /// No class is set. The class is derived from the CPU scheduling policy.
+    None = 0,
+    /// The real-time class, whose requests are always dispatched first.
+    RealTime = 1,
+    /// The best-effort class, which is the class of most tasks.
+    BestEffort = 2,
+    /// The idle class, whose requests are dispatched only if no other requests are waiting.
+    Idle = 3,
+}
+
+/// The I/O priority, which consists of a class and a level within the class.
+///
+/// A lower level means a higher priority. The encoding is the same as the `ioprio` value of
+/// Linux, i.e., the class is in the top 3 bits and the level is in the low 13 bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IoPriority(u16);
+
+impl IoPriority {
+    /// The number of the levels in the real-time and the best-effort classes.
+    pub const NR_LEVELS: u16 = 8;
+
+    /// The I/O priority with no class set.
+    pub const NONE: Self = Self(0);
+
+    /// The number of the ranks, one for each level of the real-time and the best-effort classes
+    /// and one for the idle class.
+    pub(crate) const NR_RANKS: usize = Self::NR_LEVELS as usize * 2 + 1;
+
+    const CLASS_SHIFT: u16 = 13;
+    const LEVEL_MASK: u16 = (1 << Self::CLASS_SHIFT) - 1;
+    const DEFAULT_BE_LEVEL: u16 = 4;
+
+    /// Creates an I/O priority with the class and the level.
+    ///
+    /// This method returns `None` if the level is invalid for the class. The level of
+    /// [`IoPrioClass::None`] must be zero, and the level of [`IoPrioClass::Idle`] is ignored.
+    pub fn new(class: IoPrioClass, level: u16) -> Option<Self> {
+        let is_valid = match class {
+            IoPrioClass::None => level == 0,
+            IoPrioClass::RealTime | IoPrioClass::BestEffort => level < Self::NR_LEVELS,
+            IoPrioClass::Idle => level <= Self::LEVEL_MASK,
+        };
+        is_valid.then_some(Self(((class as u16) << Self::CLASS_SHIFT) | level))
+    }
+
+    /// Converts the `ioprio` value of Linux into an I/O priority.
+    pub fn from_raw(raw: u16) -> Option<Self> {
+        let class = IoPrioClass::try_from((raw >> Self::CLASS_SHIFT) as u8).ok()?;
+        Self::new(class, raw & Self::LEVEL_MASK)
+    }
+
+    /// Converts the I/O priority into the `ioprio` value of Linux.
+    pub fn to_raw(self) -> u16 {
+        self.0
+    }
+
+    /// Returns the class.
+    pub fn class(self) -> IoPrioClass {
+        IoPrioClass::try_from((self.0 >> Self::CLASS_SHIFT) as u8).unwrap()
+    }
+
+    /// Returns the level within the class.
+    pub fn level(self) -> u16 {
+        self.0 & Self::LEVEL_MASK
+    }
+
+    /// Returns whether the I/O priority is higher than `other`.
+    ///
+    /// Without a class set, the I/O priority is treated as the default best-effort one.
+    pub fn is_higher_than(self, other: Self) -> bool {
+        self.rank() < other.rank()
+    }
+
+    /// Returns the rank of the I/O priority among all the I/O priorities, where zero is the
+    /// highest.
+    pub(crate) fn rank(self) -> usize {
+        let level = self.level() as usize;
+        match self.class() {
+            IoPrioClass::RealTime => level,
+            IoPrioClass::BestEffort => Self::NR_LEVELS as usize + level,
+            IoPrioClass::None => Self::NR_LEVELS as usize + Self::DEFAULT_BE_LEVEL as usize,
+            IoPrioClass::Idle => Self::NR_RANKS - 1,
+        }
+    }
+}
+
+impl Default for IoPriority {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+static CURRENT_PRIORITY_FN: Once<fn() -> IoPriority> = Once::new();
+
+/// Registers the function that returns the I/O priority of the current task.
+///
+/// The function is called when a `Bio` is created. If the task has not set its I/O priority, the
+/// function should derive one from the CPU scheduling policy of the task.
+pub fn register_current_priority_fn(current_priority_fn: fn() -> IoPriority) {
+    CURRENT_PRIORITY_FN.call_once(|| current_priority_fn);
+}
+
+/// Returns the I/O priority of the current task.
+pub(crate) fn current_priority() -> IoPriority {
+    CURRENT_PRIORITY_FN
+        .get()
+        .map_or(IoPriority::NONE, |current_priority_fn| {
+            current_priority_fn()
+        })
+}
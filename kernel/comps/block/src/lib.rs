@@ -35,17 +35,21 @@
 extern crate alloc;
 
 pub mod bio;
+pub mod dm;
 pub mod id;
 mod impl_block_device;
+pub mod ioprio;
+pub mod partition;
 mod prelude;
 pub mod request_queue;
 
 use component::{init_component, ComponentInitError};
-use ostd::sync::SpinLock;
+use ostd::sync::{Mutex, SpinLock};
 use spin::Once;
 
 use self::{
     bio::{BioEnqueueError, SubmittedBio},
+    partition::{Partition, PartitionError},
     prelude::*,
 };
 
@@ -67,6 +71,10 @@ pub struct BlockDeviceMeta {
     pub max_nr_segments_per_bio: usize,
     /// The total number of sectors of the block device.
     pub nr_sectors: usize,
+    /// The upper limit for the number of sectors per discard bio.
+    ///
+    /// It is zero if the block device does not support discarding sectors.
+    pub max_nr_sectors_per_discard: usize,
     // Additional useful metadata can be added here in the future.
 }
 
@@ -85,6 +93,18 @@ pub fn register_device(name: String, device: Arc<dyn BlockDevice>) {
         .insert(name, device);
 }
 
+/// Unregisters the device named `name`.
+///
+/// Returns the device, or `None` if it does not exist.
+pub fn unregister_device(name: &str) -> Option<Arc<dyn BlockDevice>> {
+    COMPONENT
+        .get()
+        .unwrap()
+        .block_device_table
+        .lock()
+        .remove(name)
+}
+
 pub fn get_device(str: &str) -> Option<Arc<dyn BlockDevice>> {
     COMPONENT
         .get()
@@ -103,10 +123,75 @@ pub fn all_devices() -> Vec<(String, Arc<dyn BlockDevice>)> {
         .collect()
 }
 
+/// Reads the partition table of the device named `name` and registers its partitions.
+///
+/// The partitions registered by the previous scan are unregistered first, which fails if any of
+/// them is still in use. Like Linux, the partitions of `vda` are named `vda1`, `vda2`, etc., while
+/// the partitions of a device whose name ends with a digit (e.g., `nvme0n1`) are named with a `p`
+/// before the number (e.g., `nvme0n1p1`).
+///
+/// Returns the names of the registered partitions.
+pub fn rescan_partitions(name: &str) -> Result<Vec<String>, PartitionError> {
+    let component = COMPONENT.get().unwrap();
+    let device = get_device(name).ok_or(PartitionError::NotFound)?;
+    if device.downcast_ref::<Partition>().is_some() {
+        return Err(PartitionError::IsPartition);
+    }
+
+    // Holding the lock prevents the concurrent scans of the same device.
+    let mut partition_table = component.partition_table.lock();
+    let entries = partition::read_partition_table(device.as_ref())?;
+
+    let mut block_devs = component.block_device_table.lock();
+    let old_names = partition_table.remove(name).unwrap_or_default();
+    // The table holds one reference, so the other references mean that the partition is in use,
+    // e.g., mounted or opened.
+    if old_names.iter().any(|old_name| {
+        block_devs
+            .get(old_name)
+            .is_some_and(|partition| Arc::strong_count(partition) > 1)
+    }) {
+        partition_table.insert(name.to_string(), old_names);
+        return Err(PartitionError::Busy);
+    }
+    for old_name in old_names.iter() {
+        block_devs.remove(old_name);
+    }
+
+    let separator = if name.ends_with(|c: char| c.is_ascii_digit()) {
+        "p"
+    } else {
+        ""
+    };
+    let mut new_names = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let partition_name = format!("{}{}{}", name, separator, entry.number);
+        let partition = Partition::new(device.clone(), entry.sid_range);
+        block_devs.insert(partition_name.clone(), Arc::new(partition));
+        new_names.push(partition_name);
+    }
+    partition_table.insert(name.to_string(), new_names.clone());
+
+    Ok(new_names)
+}
+
+/// Returns the names of the partitions of the device named `name`.
+pub fn partitions_of(name: &str) -> Vec<String> {
+    COMPONENT
+        .get()
+        .unwrap()
+        .partition_table
+        .lock()
+        .get(name)
+        .cloned()
+        .unwrap_or_default()
+}
+
 static COMPONENT: Once<Component> = Once::new();
 
 #[init_component]
 fn component_init() -> Result<(), ComponentInitError> {
+    aster_trace::register_tracepoints(&[&bio::BIO_SUBMIT, &bio::BIO_COMPLETE]);
     let a = Component::init()?;
     COMPONENT.call_once(|| a);
     Ok(())
@@ -115,12 +200,15 @@ fn component_init() -> Result<(), ComponentInitError> {
 #[derive(Debug)]
 struct Component {
     block_device_table: SpinLock<BTreeMap<String, Arc<dyn BlockDevice>>>,
+    /// The names of the partitions of each device.
+    partition_table: Mutex<BTreeMap<String, Vec<String>>>,
 }
 
 impl Component {
     pub fn init() -> Result<Self, ComponentInitError> {
         Ok(Self {
             block_device_table: SpinLock::new(BTreeMap::new()),
+            partition_table: Mutex::new(BTreeMap::new()),
         })
     }
 }
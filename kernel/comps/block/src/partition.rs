@@ -0,0 +1,381 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The partitions of block devices.
+//!
+//! A block device may be divided into partitions by the partition table at its beginning. Both
+//! the master boot record (MBR), including the logical partitions in the extended partition, and
+//! the GUID partition table (GPT) are supported. Like Linux, the GPT is only recognized if the
+//! MBR is a protective one, and the backup GPT at the end of the device is used if the primary
+//! one is corrupted.
+//!
+//! Each partition is a [`Partition`], which forwards the requests to the parent device.
+
+use ostd::mm::VmIo;
+
+use crate::{
+    bio::{BioEnqueueError, BioStatus, SubmittedBio},
+    id::Sid,
+    prelude::*,
+    BlockDevice, BlockDeviceMeta, SECTOR_SIZE,
+};
+
+/// A partition of a block device.
+#[derive(Debug)]
+pub struct Partition {
+    parent: Arc<dyn BlockDevice>,
+    sid_range: Range<Sid>,
+}
+
+impl Partition {
+    /// Creates a partition that consists of the sectors in `sid_range` of the `parent` device.
+    pub fn new(parent: Arc<dyn BlockDevice>, sid_range: Range<Sid>) -> Self {
+        Self { parent, sid_range }
+    }
+
+    /// Returns the range of the sectors on the parent device.
+    pub fn sid_range(&self) -> &Range<Sid> {
+        &self.sid_range
+    }
+
+    fn nr_sectors(&self) -> u64 {
+        self.sid_range.end.to_raw() - self.sid_range.start.to_raw()
+    }
+}
+
+impl BlockDevice for Partition {
+    fn enqueue(&self, bio: SubmittedBio) -> Result<(), BioEnqueueError> {
+        // Like Linux, the requests beyond the end of the partition fail instead of touching the
+        // sectors of other partitions.
+        if bio.sid_range().end.to_raw() > self.nr_sectors() {
+            bio.complete(BioStatus::IoError);
+            return Ok(());
+        }
+
+        bio.remap_and_submit(self.parent.as_ref(), self.sid_range.start.to_raw())
+    }
+
+    fn metadata(&self) -> BlockDeviceMeta {
+        BlockDeviceMeta {
+            nr_sectors: self.nr_sectors() as usize,
+            ..self.parent.metadata()
+        }
+    }
+}
+
+/// An entry in the partition table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionEntry {
+    /// The partition number, which starts from one.
+    ///
+    /// For the MBR, the numbers of the logical partitions start from five, as in Linux.
+    pub number: usize,
+    /// The range of the sectors on the device.
+    pub sid_range: Range<Sid>,
+}
+
+/// The errors of the partition operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionError {
+    /// The device does not exist.
+    NotFound,
+    /// The device is a partition, which cannot have partitions.
+    IsPartition,
+    /// The partition table cannot be read from the device.
+    IoError,
+    /// Some partitions of the device are in use.
+    Busy,
+}
+
+/// Reads the partition table of the device.
+///
+/// Returns an empty list if the device has no partition table. The partitions that are empty or
+/// extend beyond the end of the device are ignored.
+pub fn read_partition_table(
+    device: &dyn BlockDevice,
+) -> Result<Vec<PartitionEntry>, PartitionError> {
+    let nr_sectors = device.metadata().nr_sectors as u64;
+    if nr_sectors == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mbr = read_sectors(device, 0, 1)?;
+    if mbr[MBR_SIGNATURE_OFFSET..] != MBR_SIGNATURE {
+        return Ok(Vec::new());
+    }
+
+    let mbr_entries = parse_mbr_entries(&mbr);
+    // Like Linux, invalid boot indicators mean that the sector is not an MBR, e.g., it is the
+    // boot sector of a FAT or exFAT file system, which also has the signature.
+    if mbr_entries
+        .iter()
+        .any(|entry| entry.boot_indicator != 0x00 && entry.boot_indicator != 0x80)
+    {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = if mbr_entries
+        .iter()
+        .any(|entry| entry.type_ == MBR_TYPE_GPT_PROTECTIVE)
+    {
+        read_gpt(device, nr_sectors)?
+    } else {
+        read_mbr(device, &mbr_entries, nr_sectors)?
+    };
+
+    entries.retain(|entry| {
+        let is_valid = entry.sid_range.start < entry.sid_range.end
+            && entry.sid_range.end.to_raw() <= nr_sectors;
+        if !is_valid {
+            log::warn!(
+                "partition {} ({:?}) is beyond the end of the device, ignored",
+                entry.number,
+                entry.sid_range
+            );
+        }
+        is_valid
+    });
+    Ok(entries)
+}
+
+const MBR_ENTRIES_OFFSET: usize = 446;
+const MBR_ENTRY_SIZE: usize = 16;
+const MBR_SIGNATURE_OFFSET: usize = 510;
+const MBR_SIGNATURE: [u8; 2] = [0x55, 0xaa];
+
+const MBR_TYPE_EMPTY: u8 = 0x00;
+const MBR_TYPE_GPT_PROTECTIVE: u8 = 0xee;
+/// The types of the extended partitions, i.e., DOS, Windows (LBA), and Linux.
+const MBR_TYPES_EXTENDED: [u8; 3] = [0x05, 0x0f, 0x85];
+
+/// The maximum number of the logical partitions, which stops the loops in the EBR chain.
+const MAX_LOGICAL_PARTITIONS: usize = 128;
+
+/// An entry in the MBR or an EBR.
+#[derive(Debug, Clone, Copy)]
+struct MbrEntry {
+    boot_indicator: u8,
+    type_: u8,
+    /// The first sector, which is relative to different bases for different entries.
+    start: u64,
+    nr_sectors: u64,
+}
+
+fn parse_mbr_entries(sector: &[u8]) -> [MbrEntry; 4] {
+    core::array::from_fn(|i| {
+        let entry = &sector[MBR_ENTRIES_OFFSET + i * MBR_ENTRY_SIZE..][..MBR_ENTRY_SIZE];
+        MbrEntry {
+            boot_indicator: entry[0],
+            type_: entry[4],
+            start: read_u32(entry, 8) as u64,
+            nr_sectors: read_u32(entry, 12) as u64,
+        }
+    })
+}
+
+fn read_mbr(
+    device: &dyn BlockDevice,
+    mbr_entries: &[MbrEntry; 4],
+    nr_sectors: u64,
+) -> Result<Vec<PartitionEntry>, PartitionError> {
+    let mut entries = Vec::new();
+
+    for (i, entry) in mbr_entries.iter().enumerate() {
+        if entry.type_ == MBR_TYPE_EMPTY || entry.nr_sectors == 0 {
+            continue;
+        }
+        if MBR_TYPES_EXTENDED.contains(&entry.type_) {
+            read_logical_partitions(device, entry.start, nr_sectors, &mut entries)?;
+            continue;
+        }
+
+        entries.push(PartitionEntry {
+            number: i + 1,
+            sid_range: sid_range(entry.start, entry.nr_sectors),
+        });
+    }
+
+    entries.sort_by_key(|entry| entry.number);
+    Ok(entries)
+}
+
+/// Reads the logical partitions in the extended partition that starts at `extended_start`.
+///
+/// Each logical partition is described by an extended boot record (EBR), whose first entry is
+/// the logical partition relative to the EBR, and whose second entry points to the next EBR
+/// relative to the extended partition.
+fn read_logical_partitions(
+    device: &dyn BlockDevice,
+    extended_start: u64,
+    nr_sectors: u64,
+    entries: &mut Vec<PartitionEntry>,
+) -> Result<(), PartitionError> {
+    let mut ebr_start = extended_start;
+    let mut number = 5;
+
+    for _ in 0..MAX_LOGICAL_PARTITIONS {
+        if ebr_start >= nr_sectors {
+            break;
+        }
+        let ebr = read_sectors(device, ebr_start, 1)?;
+        if ebr[MBR_SIGNATURE_OFFSET..] != MBR_SIGNATURE {
+            break;
+        }
+
+        let [logical, next, ..] = parse_mbr_entries(&ebr);
+        if logical.type_ != MBR_TYPE_EMPTY && logical.nr_sectors != 0 {
+            entries.push(PartitionEntry {
+                number,
+                sid_range: sid_range(ebr_start + logical.start, logical.nr_sectors),
+            });
+            number += 1;
+        }
+
+        if !MBR_TYPES_EXTENDED.contains(&next.type_) || next.start == 0 {
+            break;
+        }
+        ebr_start = extended_start + next.start;
+    }
+
+    Ok(())
+}
+
+const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+const GPT_MIN_HEADER_SIZE: usize = 92;
+const GPT_MIN_ENTRY_SIZE: usize = 128;
+/// The maximum size of the partition entry array, which is large enough for real disks.
+const GPT_MAX_ENTRIES_BYTES: usize = 1024 * 1024;
+
+fn read_gpt(
+    device: &dyn BlockDevice,
+    nr_sectors: u64,
+) -> Result<Vec<PartitionEntry>, PartitionError> {
+    if let Some(entries) = read_gpt_at(device, 1, nr_sectors)? {
+        return Ok(entries);
+    }
+
+    log::warn!("the primary GPT is corrupted, trying the backup GPT");
+    if let Some(entries) = read_gpt_at(device, nr_sectors - 1, nr_sectors)? {
+        return Ok(entries);
+    }
+
+    log::warn!("the backup GPT is corrupted, no partitions are found");
+    Ok(Vec::new())
+}
+
+/// Reads the GPT whose header is at `header_lba`.
+///
+/// Returns `None` if the GPT is corrupted.
+fn read_gpt_at(
+    device: &dyn BlockDevice,
+    header_lba: u64,
+    nr_sectors: u64,
+) -> Result<Option<Vec<PartitionEntry>>, PartitionError> {
+    let header = read_sectors(device, header_lba, 1)?;
+
+    if &header[..8] != GPT_SIGNATURE {
+        return Ok(None);
+    }
+    let header_size = read_u32(&header, 12) as usize;
+    if !(GPT_MIN_HEADER_SIZE..=SECTOR_SIZE).contains(&header_size) {
+        return Ok(None);
+    }
+    // The checksum is calculated with the checksum field being zero.
+    let mut header_copy = header[..header_size].to_vec();
+    header_copy[16..20].fill(0);
+    if crc32(&header_copy) != read_u32(&header, 16) {
+        return Ok(None);
+    }
+    if read_u64(&header, 24) != header_lba {
+        return Ok(None);
+    }
+
+    let last_usable_lba = read_u64(&header, 48);
+    let entries_lba = read_u64(&header, 72);
+    let nr_entries = read_u32(&header, 80) as usize;
+    let entry_size = read_u32(&header, 84) as usize;
+    let entries_crc = read_u32(&header, 88);
+    if entry_size < GPT_MIN_ENTRY_SIZE
+        || !entry_size.is_power_of_two()
+        || nr_entries
+            .checked_mul(entry_size)
+            .is_none_or(|size| size > GPT_MAX_ENTRIES_BYTES)
+    {
+        return Ok(None);
+    }
+
+    let entries_bytes = nr_entries * entry_size;
+    let entries_sectors = entries_bytes.div_ceil(SECTOR_SIZE) as u64;
+    if entries_lba
+        .checked_add(entries_sectors)
+        .is_none_or(|end| end > nr_sectors)
+    {
+        return Ok(None);
+    }
+    let raw_entries = read_sectors(device, entries_lba, entries_sectors as usize)?;
+    if crc32(&raw_entries[..entries_bytes]) != entries_crc {
+        return Ok(None);
+    }
+
+    let mut entries = Vec::new();
+    for (i, entry) in raw_entries[..entries_bytes]
+        .chunks_exact(entry_size)
+        .enumerate()
+    {
+        // An entry with the zero partition type GUID is unused.
+        if entry[..16].iter().all(|&byte| byte == 0) {
+            continue;
+        }
+
+        // The last LBA is inclusive.
+        let first_lba = read_u64(entry, 32);
+        let last_lba = read_u64(entry, 40);
+        if first_lba > last_lba || last_lba > last_usable_lba || last_lba >= nr_sectors {
+            log::warn!("GPT entry {} has an invalid range, ignored", i);
+            continue;
+        }
+
+        entries.push(PartitionEntry {
+            number: i + 1,
+            sid_range: Sid::new(first_lba)..Sid::new(last_lba + 1),
+        });
+    }
+
+    Ok(Some(entries))
+}
+
+fn read_sectors(
+    device: &dyn BlockDevice,
+    sid: u64,
+    nr_sectors: usize,
+) -> Result<Vec<u8>, PartitionError> {
+    let mut buf = vec![0u8; nr_sectors * SECTOR_SIZE];
+    device
+        .read_bytes(sid as usize * SECTOR_SIZE, &mut buf)
+        .map_err(|_| PartitionError::IoError)?;
+    Ok(buf)
+}
+
+fn sid_range(start: u64, nr_sectors: u64) -> Range<Sid> {
+    Sid::new(start)..Sid::new(start + nr_sectors)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+/// Calculates the CRC-32 (i.e., the one used by Ethernet and zlib) of the bytes.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
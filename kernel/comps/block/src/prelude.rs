@@ -1,8 +1,10 @@
 // SPDX-License-Identifier: MPL-2.0
 
 pub(crate) use alloc::{
+    boxed::Box,
     collections::{BTreeMap, VecDeque},
-    string::String,
+    format,
+    string::{String, ToString},
     sync::Arc,
     vec,
     vec::Vec,
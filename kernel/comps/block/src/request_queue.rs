@@ -1,23 +1,45 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use ostd::sync::{Mutex, WaitQueue};
+use core::time::Duration;
+
+use ostd::{
+    sync::{Mutex, WaitQueue},
+    timer::Jiffies,
+};
 
 use super::{
     bio::{BioEnqueueError, BioType, SubmittedBio},
     id::Sid,
+    ioprio::IoPriority,
 };
 use crate::prelude::*;
 
-/// A simple block I/O request queue backed by one internal FIFO queue.
+/// The time that a read request can wait before it is dispatched regardless of its I/O priority.
+///
+/// This is the same as the default `read_expire` of the mq-deadline scheduler in Linux.
+const READ_EXPIRE: Duration = Duration::from_millis(500);
+
+/// The time that a request other than reads can wait before it is dispatched regardless of its
+/// I/O priority.
 ///
-/// It is a FIFO producer-consumer queue, where the producer (e.g., filesystem)
+/// This is the same as the default `write_expire` of the mq-deadline scheduler in Linux.
+const WRITE_EXPIRE: Duration = Duration::from_secs(5);
+
+/// A block I/O request queue that dispatches the requests by their I/O priorities.
+///
+/// It is a producer-consumer queue, where the producer (e.g., filesystem)
 /// submits requests to the queue, and the consumer (e.g., block device driver)
 /// continuously consumes and processes these requests from the queue.
 ///
-/// It supports merging the new request with the front request if if the type
-/// is same and the sector range is contiguous.
+/// The requests of a higher I/O priority are dispatched first, and the requests of the same I/O
+/// priority are dispatched in the FIFO order. So the reads of the interactive tasks are not
+/// delayed by the background writeback. Still, a request that has waited past its deadline is
+/// dispatched before any others, so that the requests of lower I/O priorities do not starve.
+///
+/// It supports merging the new request with the newest request of the same I/O priority if the
+/// type is same and the sector range is contiguous.
 pub struct BioRequestSingleQueue {
-    queue: Mutex<VecDeque<BioRequest>>,
+    queue: Mutex<PrioQueues>,
     num_requests: AtomicUsize,
     wait_queue: WaitQueue,
     max_nr_segments_per_bio: usize,
@@ -32,7 +54,7 @@ impl BioRequestSingleQueue {
     /// Creates an empty queue with the upper bound for the number of segments in a bio.
     pub fn with_max_nr_segments_per_bio(max_nr_segments_per_bio: usize) -> Self {
         Self {
-            queue: Mutex::new(VecDeque::new()),
+            queue: Mutex::new(PrioQueues::new()),
             num_requests: AtomicUsize::new(0),
             wait_queue: WaitQueue::new(),
             max_nr_segments_per_bio,
@@ -51,8 +73,8 @@ impl BioRequestSingleQueue {
 
     /// Enqueues a `SubmittedBio` to this queue.
     ///
-    /// When enqueueing the `SubmittedBio`, try to insert it into the last request if the
-    /// type is same and the sector range is contiguous.
+    /// When enqueueing the `SubmittedBio`, try to insert it into the last request of the same
+    /// I/O priority if the type is same and the sector range is contiguous.
     /// Otherwise, creates and inserts a new request for the `SubmittedBio`.
     ///
     /// This method will wake up the waiter if a new `BioRequest` is enqueued.
@@ -61,7 +83,8 @@ impl BioRequestSingleQueue {
             return Err(BioEnqueueError::TooBig);
         }
 
-        let mut queue = self.queue.lock();
+        let mut queues = self.queue.lock();
+        let queue = queues.queue_mut(bio.priority());
         if let Some(request) = queue.front_mut() {
             if request.can_merge(&bio)
                 && request.num_segments() + bio.segments().len() <= self.max_nr_segments_per_bio
@@ -74,7 +97,7 @@ impl BioRequestSingleQueue {
         let new_request = BioRequest::from(bio);
         queue.push_front(new_request);
         self.inc_num_requests();
-        drop(queue);
+        drop(queues);
 
         self.wait_queue.wake_all();
         Ok(())
@@ -84,28 +107,31 @@ impl BioRequestSingleQueue {
     ///
     /// This method will wait until one request can be retrieved.
     pub fn dequeue(&self) -> BioRequest {
-        let mut num_requests = self.num_requests();
-
         loop {
-            if num_requests > 0 {
-                let mut queue = self.queue.lock();
-                if let Some(request) = queue.pop_back() {
-                    self.dec_num_requests();
-                    return request;
-                }
+            if let Some(request) = self.try_dequeue() {
+                return request;
             }
 
-            num_requests = self.wait_queue.wait_until(|| {
-                let num_requests = self.num_requests();
-                if num_requests > 0 {
-                    Some(num_requests)
-                } else {
-                    None
-                }
-            });
+            self.wait_queue
+                .wait_until(|| (self.num_requests() > 0).then_some(()));
         }
     }
 
+    /// Dequeues a `BioRequest` from this queue without waiting.
+    ///
+    /// Returns `None` if the queue is empty. The block device driver can use
+    /// this method to dequeue more requests after [`Self::dequeue`] returns,
+    /// so that the requests are submitted to the device in a batch.
+    pub fn try_dequeue(&self) -> Option<BioRequest> {
+        if self.num_requests() == 0 {
+            return None;
+        }
+
+        let request = self.queue.lock().pop()?;
+        self.dec_num_requests();
+        Some(request)
+    }
+
     fn dec_num_requests(&self) {
         self.num_requests.fetch_sub(1, Ordering::Relaxed);
     }
@@ -115,6 +141,39 @@ impl BioRequestSingleQueue {
     }
 }
 
+/// The FIFO queues of the requests, one for each rank of the I/O priorities.
+#[derive(Debug)]
+struct PrioQueues([VecDeque<BioRequest>; IoPriority::NR_RANKS]);
+
+impl PrioQueues {
+    fn new() -> Self {
+        Self(core::array::from_fn(|_| VecDeque::new()))
+    }
+
+    fn queue_mut(&mut self, priority: IoPriority) -> &mut VecDeque<BioRequest> {
+        &mut self.0[priority.rank()]
+    }
+
+    /// Pops the request that should be dispatched next.
+    ///
+    /// The expired request with the earliest deadline is popped if there is one. Otherwise, the
+    /// oldest request of the highest I/O priority is popped.
+    fn pop(&mut self) -> Option<BioRequest> {
+        let now = Jiffies::elapsed().as_duration();
+        let expired_rank = self
+            .0
+            .iter()
+            .enumerate()
+            .filter_map(|(rank, queue)| Some((rank, queue.back()?.deadline)))
+            .filter(|(_, deadline)| *deadline <= now)
+            .min_by_key(|(_, deadline)| *deadline)
+            .map(|(rank, _)| rank);
+
+        let rank = expired_rank.or_else(|| self.0.iter().position(|queue| !queue.is_empty()))?;
+        self.0[rank].pop_back()
+    }
+}
+
 impl Default for BioRequestSingleQueue {
     fn default() -> Self {
         Self::new()
@@ -139,6 +198,10 @@ impl Debug for BioRequestSingleQueue {
 pub struct BioRequest {
     /// The type of the I/O
     type_: BioType,
+    /// The I/O priority
+    priority: IoPriority,
+    /// The time since boot when the request should be dispatched regardless of its I/O priority
+    deadline: Duration,
     /// The range of target sectors on the device
     sid_range: Range<Sid>,
     /// The number of segments
@@ -153,6 +216,11 @@ impl BioRequest {
         self.type_
     }
 
+    /// Returns the I/O priority.
+    pub fn priority(&self) -> IoPriority {
+        self.priority
+    }
+
     /// Returns the range of sector id on device.
     pub fn sid_range(&self) -> &Range<Sid> {
         &self.sid_range
@@ -176,8 +244,14 @@ impl BioRequest {
     }
 
     /// Returns `true` if can merge the `SubmittedBio`, `false` otherwise.
+    ///
+    /// The discard bios are never merged, since the block device limits the number of sectors
+    /// per discard (see `BlockDeviceMeta::max_nr_sectors_per_discard`).
     pub fn can_merge(&self, rq_bio: &SubmittedBio) -> bool {
-        if rq_bio.type_() != self.type_ {
+        if rq_bio.type_() != self.type_ || self.type_ == BioType::Discard {
+            return false;
+        }
+        if rq_bio.priority().rank() != self.priority.rank() {
             return false;
         }
 
@@ -211,8 +285,15 @@ impl BioRequest {
 
 impl From<SubmittedBio> for BioRequest {
     fn from(bio: SubmittedBio) -> Self {
+        let expire = match bio.type_() {
+            BioType::Read => READ_EXPIRE,
+            BioType::Write | BioType::Flush | BioType::Discard => WRITE_EXPIRE,
+        };
+
         Self {
             type_: bio.type_(),
+            priority: bio.priority(),
+            deadline: Jiffies::elapsed().as_duration() + expire,
             sid_range: bio.sid_range().clone(),
             num_segments: bio.segments().len(),
             bios: {
@@ -7,16 +7,32 @@
 
 extern crate alloc;
 
-use alloc::{collections::BTreeMap, fmt::Debug, string::String, sync::Arc, vec::Vec};
+mod selection;
+mod serial;
+
+use alloc::{
+    collections::BTreeMap,
+    fmt::Debug,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
 use core::any::Any;
 
 use component::{init_component, ComponentInitError};
 use ostd::{
+    boot::boot_info,
+    early_println,
     mm::{Infallible, VmReader},
     sync::{LocalIrqDisabled, SpinLock, SpinLockGuard},
 };
 use spin::Once;
 
+use self::{
+    selection::ConsoleSelection,
+    serial::{SerialConsole, SERIAL_CONSOLE_NAME},
+};
+
 pub type ConsoleCallback = dyn Fn(VmReader<Infallible>) + Send + Sync;
 
 pub trait AnyConsoleDevice: Send + Sync + Any + Debug {
@@ -29,37 +45,122 @@ pub trait AnyConsoleDevice: Send + Sync + Any + Debug {
     fn register_callback(&self, callback: &'static ConsoleCallback);
 }
 
+/// The options of a console selected by `console=`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsoleOptions {
+    /// The name of the console, e.g., `hvc0`.
+    pub name: String,
+    /// The console log level of the console, which overrides the global one.
+    pub level: Option<u8>,
+}
+
+/// The table of the console devices.
+#[derive(Debug)]
+pub struct ConsoleTable {
+    devices: BTreeMap<String, Arc<dyn AnyConsoleDevice>>,
+    selection: ConsoleSelection,
+}
+
+impl ConsoleTable {
+    /// Returns the selected consoles that are available, from the most preferred one, with their
+    /// console log levels.
+    ///
+    /// All the consoles are selected if no console is given by `console=`.
+    fn selected(&self) -> impl Iterator<Item = (&Arc<dyn AnyConsoleDevice>, Option<u8>)> {
+        let select_all = self.selection.consoles.is_empty();
+        let all = self
+            .devices
+            .values()
+            .filter(move |_| select_all)
+            .map(|device| (device, None));
+        let selected = self
+            .selection
+            .consoles
+            .iter()
+            .filter_map(|options| Some((self.devices.get(&options.name)?, options.level)));
+        all.chain(selected)
+    }
+
+    /// Returns whether the messages are printed on the early console.
+    ///
+    /// The early console is used if it is enabled by `earlycon` and no selected console is
+    /// available yet.
+    pub fn is_early(&self) -> bool {
+        self.selection.earlycon && self.selected().next().is_none()
+    }
+
+    /// Returns the consoles on which the kernel messages are printed, with their console log
+    /// levels.
+    ///
+    /// If none of the selected consoles is available and the early console is not enabled, the
+    /// messages fall back to all the consoles, so that they are not lost.
+    pub fn message_consoles(
+        &self,
+    ) -> impl Iterator<Item = (&Arc<dyn AnyConsoleDevice>, Option<u8>)> {
+        let fallback = !self.selection.earlycon && self.selected().next().is_none();
+        self.selected().chain(
+            self.devices
+                .values()
+                .filter(move |_| fallback)
+                .map(|device| (device, None)),
+        )
+    }
+
+    /// Returns the consoles to which the output of `/dev/console` is sent.
+    ///
+    /// The output is sent to the most preferred console that is available. If no console is
+    /// given by `console=` or none of them is available, the output is sent to all the consoles.
+    pub fn tty_consoles(&self) -> impl Iterator<Item = &Arc<dyn AnyConsoleDevice>> {
+        let preferred = if self.selection.consoles.is_empty() {
+            None
+        } else {
+            self.selected().next().map(|(device, _)| device)
+        };
+        let fallback = preferred.is_none();
+        preferred
+            .into_iter()
+            .chain(self.devices.values().filter(move |_| fallback))
+    }
+}
+
 pub fn register_device(name: String, device: Arc<dyn AnyConsoleDevice>) {
-    COMPONENT
-        .get()
-        .unwrap()
-        .console_device_table
-        .disable_irq()
-        .lock()
-        .insert(name, device);
+    let mut table = console_table_lock();
+    let was_early = table.is_early();
+    table.devices.insert(name.clone(), device);
+
+    // The messages are printed on the new console from now on, so the early console stops
+    // before any message is printed on both of them.
+    if was_early && !table.is_early() {
+        early_println!("[console] {} enabled, earlycon disabled", name);
+    }
 }
 
 pub fn all_devices() -> Vec<(String, Arc<dyn AnyConsoleDevice>)> {
-    let console_devs = COMPONENT
-        .get()
-        .unwrap()
-        .console_device_table
-        .disable_irq()
-        .lock();
-    console_devs
+    let table = console_table_lock();
+    table
+        .devices
         .iter()
         .map(|(name, device)| (name.clone(), device.clone()))
         .collect()
 }
 
-pub fn all_devices_lock<'a>(
-) -> SpinLockGuard<'a, BTreeMap<String, Arc<dyn AnyConsoleDevice>>, LocalIrqDisabled> {
+/// Locks the table of all console devices.
+pub fn console_table_lock<'a>() -> SpinLockGuard<'a, ConsoleTable, LocalIrqDisabled> {
+    COMPONENT.get().unwrap().console_table.disable_irq().lock()
+}
+
+/// Tries to lock the table of all console devices without spinning.
+///
+/// This returns `None` if the lock is held, e.g., by another CPU that is printing or by the
+/// interrupted code on the current CPU. Callers that may run in the interrupt context should use
+/// this method to avoid deadlocks.
+pub fn try_console_table_lock<'a>() -> Option<SpinLockGuard<'a, ConsoleTable, LocalIrqDisabled>> {
     COMPONENT
         .get()
         .unwrap()
-        .console_device_table
+        .console_table
         .disable_irq()
-        .lock()
+        .try_lock()
 }
 
 static COMPONENT: Once<Component> = Once::new();
@@ -68,18 +169,33 @@ static COMPONENT: Once<Component> = Once::new();
 fn component_init() -> Result<(), ComponentInitError> {
     let a = Component::init()?;
     COMPONENT.call_once(|| a);
+
+    // The serial console is only used if it is selected, since the serial port may be connected
+    // to the same terminal as the other consoles.
+    let is_serial_selected = console_table_lock()
+        .selection
+        .consoles
+        .iter()
+        .any(|options| options.name == SERIAL_CONSOLE_NAME);
+    if is_serial_selected {
+        register_device(SERIAL_CONSOLE_NAME.to_string(), Arc::new(SerialConsole));
+    }
+
     Ok(())
 }
 
 #[derive(Debug)]
 struct Component {
-    console_device_table: SpinLock<BTreeMap<String, Arc<dyn AnyConsoleDevice>>>,
+    console_table: SpinLock<ConsoleTable>,
 }
 
 impl Component {
     pub fn init() -> Result<Self, ComponentInitError> {
         Ok(Self {
-            console_device_table: SpinLock::new(BTreeMap::new()),
+            console_table: SpinLock::new(ConsoleTable {
+                devices: BTreeMap::new(),
+                selection: ConsoleSelection::parse(&boot_info().kernel_cmdline),
+            }),
         })
     }
 }
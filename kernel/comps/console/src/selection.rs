@@ -0,0 +1,103 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The selection of the consoles with the kernel command line.
+//!
+//! Like Linux, the consoles are selected with `console=<name>[,<options>]`, which can be given
+//! more than once. The kernel messages are printed on all the selected consoles, and the last one
+//! is the preferred console, i.e., `/dev/console`. Besides the options of Linux (e.g., the baud
+//! rate, which is ignored), `loglevel:<level>` sets the console log level of the console.
+//!
+//! With `earlycon`, the messages are printed on the serial port of the platform before any
+//! selected console is available.
+//!
+//! The component is initialized before the kernel, so the arguments are parsed here in the same
+//! way as the kernel parses them.
+
+use alloc::{string::ToString, vec::Vec};
+
+use crate::ConsoleOptions;
+
+/// The consoles and the early console selected by the kernel command line.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct ConsoleSelection {
+    /// The selected consoles, from the most preferred one.
+    pub(crate) consoles: Vec<ConsoleOptions>,
+    /// Whether the early console is enabled.
+    pub(crate) earlycon: bool,
+}
+
+impl ConsoleSelection {
+    /// Parses the selection from the kernel command line.
+    pub(crate) fn parse(cmdline: &str) -> Self {
+        let mut selection = Self::default();
+
+        // The arguments after `--` are for the init process.
+        for arg in cmdline.split_whitespace().take_while(|arg| *arg != "--") {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (arg, None),
+            };
+            match (name, value) {
+                ("earlycon", None) => selection.earlycon = true,
+                ("earlycon", Some(value)) => {
+                    selection.earlycon = !matches!(value, "0" | "n" | "N" | "off" | "false");
+                }
+                ("console", Some(value)) => {
+                    let options = parse_console_options(value);
+                    // The console given later is preferred.
+                    selection
+                        .consoles
+                        .retain(|other| other.name != options.name);
+                    selection.consoles.insert(0, options);
+                }
+                _ => (),
+            }
+        }
+
+        selection
+    }
+}
+
+fn parse_console_options(value: &str) -> ConsoleOptions {
+    let mut fields = value.split(',');
+    let name = fields.next().unwrap_or_default().to_string();
+    let level = fields
+        .filter_map(|field| field.strip_prefix("loglevel:"))
+        .filter_map(|level| level.parse().ok())
+        .last();
+    ConsoleOptions { name, level }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn parse_selection() {
+        let selection = ConsoleSelection::parse(
+            "console=ttyS0,115200n8,loglevel:5 earlycon console=tty0 console=hvc0 -- console=x",
+        );
+        assert!(selection.earlycon);
+        let names: Vec<_> = selection
+            .consoles
+            .iter()
+            .map(|options| options.name.as_str())
+            .collect();
+        assert_eq!(names, ["hvc0", "tty0", "ttyS0"]);
+        assert_eq!(selection.consoles[2].level, Some(5));
+        assert_eq!(selection.consoles[0].level, None);
+
+        // The console given again is moved to the front.
+        let selection = ConsoleSelection::parse("console=hvc0 console=ttyS0 console=hvc0");
+        assert_eq!(selection.consoles.len(), 2);
+        assert_eq!(selection.consoles[0].name, "hvc0");
+        assert!(!selection.earlycon);
+
+        assert_eq!(
+            ConsoleSelection::parse("earlycon=off"),
+            ConsoleSelection::default()
+        );
+    }
+}
@@ -0,0 +1,32 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The console on the serial port of the platform, i.e., `ttyS0`.
+
+use ostd::console::early_print;
+
+use crate::{AnyConsoleDevice, ConsoleCallback};
+
+/// The name of the serial console, which is the same as Linux.
+pub const SERIAL_CONSOLE_NAME: &str = "ttyS0";
+
+/// The console that sends the output to the serial port with which OSTD prints early messages.
+///
+/// The console is available as soon as the component is initialized, so it does not need an
+/// early console to hand over from.
+#[derive(Debug)]
+pub(crate) struct SerialConsole;
+
+impl AnyConsoleDevice for SerialConsole {
+    fn send(&self, buf: &[u8]) {
+        for chunk in buf.utf8_chunks() {
+            early_print(format_args!("{}", chunk.valid()));
+            if !chunk.invalid().is_empty() {
+                early_print(format_args!("{}", char::REPLACEMENT_CHARACTER));
+            }
+        }
+    }
+
+    fn register_callback(&self, _: &'static ConsoleCallback) {
+        // FIXME: Receive the input from the serial port, which requires its interrupts.
+    }
+}
@@ -24,7 +24,8 @@ pub struct FramebufferConsole {
     state: SpinLock<ConsoleState, LocalIrqDisabled>,
 }
 
-pub static CONSOLE_NAME: &str = "Framebuffer-Console";
+/// The name of the console, which is used by `console=` as in Linux.
+pub static CONSOLE_NAME: &str = "tty0";
 
 pub static FRAMEBUFFER_CONSOLE: Once<Arc<FramebufferConsole>> = Once::new();
 
@@ -14,8 +14,9 @@ impl log::Log for AsterLogger {
     }
 
     fn log(&self, record: &Record) {
-        let timestamp = Jiffies::elapsed().as_duration().as_secs_f64();
-        print_logs(record, timestamp);
+        let timestamp = Jiffies::elapsed().as_duration();
+        crate::record::record_log(record.level(), *record.args(), timestamp);
+        print_logs(record, timestamp.as_secs_f64());
     }
 
     fn flush(&self) {}
@@ -25,6 +26,7 @@ impl log::Log for AsterLogger {
 fn print_logs(record: &Record, timestamp: f64) {
     use owo_colors::Style;
 
+    let level = crate::record::syslog_level(record.level());
     let timestamp_style = Style::new().green();
     let record_style = Style::new().default_color();
     let level_style = match record.level() {
@@ -35,22 +37,29 @@ fn print_logs(record: &Record, timestamp: f64) {
         log::Level::Trace => Style::new().bright_black(),
     };
 
-    super::_print(format_args!(
-        "{} {:<5}: {}\n",
-        timestamp_style.style(format_args!("[{:>10.3}]", timestamp)),
-        level_style.style(record.level()),
-        record_style.style(record.args())
-    ));
+    super::print_at_level(
+        Some(level),
+        format_args!(
+            "{} {:<5}: {}\n",
+            timestamp_style.style(format_args!("[{:>10.3}]", timestamp)),
+            level_style.style(record.level()),
+            record_style.style(record.args())
+        ),
+    );
 }
 
 #[cfg(not(feature = "log_color"))]
 fn print_logs(record: &Record, timestamp: f64) {
-    super::_print(format_args!(
-        "{} {:<5}: {}\n",
-        format_args!("[{:>10.3}]", timestamp),
-        record.level(),
-        record.args()
-    ));
+    let level = crate::record::syslog_level(record.level());
+    super::print_at_level(
+        Some(level),
+        format_args!(
+            "{} {:<5}: {}\n",
+            format_args!("[{:>10.3}]", timestamp),
+            record.level(),
+            record.args()
+        ),
+    );
 }
 
 pub(super) fn init() {
@@ -0,0 +1,258 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Per-CPU buffers of the messages that cannot be printed immediately.
+//!
+//! A message is buffered if the consoles are busy, i.e., another CPU is printing, or the code
+//! that is printing on the current CPU is interrupted. Whoever holds the consoles prints the
+//! buffered messages before releasing them. The messages are printed in the order in which they
+//! are buffered across all CPUs.
+//!
+//! Buffering never spins or allocates memory, so it is safe in the interrupt context. If a buffer
+//! is full, the new messages are dropped and the number of the dropped messages is reported later.
+
+use core::{
+    fmt::{self, Write},
+    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+};
+
+use ostd::{
+    cpu::all_cpus,
+    cpu_local,
+    sync::{LocalIrqDisabled, SpinLock},
+    trap,
+};
+
+use crate::console::Printer;
+
+/// The number of the messages that can be buffered on each CPU.
+const NR_SLOTS: usize = 16;
+
+/// The maximum length of a buffered message in bytes.
+///
+/// Longer messages are truncated, but are still terminated with a newline.
+const SLOT_LEN: usize = 512;
+
+cpu_local! {
+    static BUFFERS: SpinLock<LogBuffer, LocalIrqDisabled> = SpinLock::new(LogBuffer::new());
+}
+
+/// The sequence number of the next buffered message, which orders the messages across CPUs.
+static NEXT_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// Whether there may be buffered messages that have not been printed.
+static HAS_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// The number of the messages that have been dropped since the last report.
+static NR_DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// A ring buffer of messages stored in fixed-size slots.
+struct LogBuffer {
+    slots: [[u8; SLOT_LEN]; NR_SLOTS],
+    lens: [usize; NR_SLOTS],
+    seqs: [u64; NR_SLOTS],
+    /// The syslog levels of the messages, or `None` if they are printed regardless of the levels.
+    levels: [Option<u8>; NR_SLOTS],
+    /// The index of the slot of the oldest message.
+    head: usize,
+    /// The number of the messages in the buffer.
+    len: usize,
+}
+
+impl LogBuffer {
+    const fn new() -> Self {
+        Self {
+            slots: [[0; SLOT_LEN]; NR_SLOTS],
+            lens: [0; NR_SLOTS],
+            seqs: [0; NR_SLOTS],
+            levels: [None; NR_SLOTS],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Formats the message into a free slot.
+    ///
+    /// This method returns `false` if the buffer is full.
+    fn push(&mut self, level: Option<u8>, args: fmt::Arguments) -> bool {
+        if self.len == NR_SLOTS {
+            return false;
+        }
+
+        let index = (self.head + self.len) % NR_SLOTS;
+        let mut writer = SlotWriter {
+            slot: &mut self.slots[index],
+            len: 0,
+        };
+        if writer.write_fmt(args).is_err() {
+            // One byte is reserved for the newline of a truncated message.
+            writer.slot[writer.len] = b'\n';
+            writer.len += 1;
+        }
+
+        self.lens[index] = writer.len;
+        self.seqs[index] = NEXT_SEQ.fetch_add(1, Ordering::Relaxed);
+        self.levels[index] = level;
+        self.len += 1;
+        true
+    }
+
+    /// Returns the sequence number of the oldest message.
+    fn front_seq(&self) -> Option<u64> {
+        (self.len > 0).then(|| self.seqs[self.head])
+    }
+
+    /// Removes the oldest message and copies it to `buf`, returning its length and its level.
+    fn pop_front(&mut self, buf: &mut [u8; SLOT_LEN]) -> Option<(usize, Option<u8>)> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let len = self.lens[self.head];
+        let level = self.levels[self.head];
+        buf[..len].copy_from_slice(&self.slots[self.head][..len]);
+        self.head = (self.head + 1) % NR_SLOTS;
+        self.len -= 1;
+        Some((len, level))
+    }
+}
+
+/// A writer that fills a slot and truncates the excessive output.
+struct SlotWriter<'a> {
+    slot: &'a mut [u8; SLOT_LEN],
+    len: usize,
+}
+
+impl Write for SlotWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let avail = SLOT_LEN - 1 - self.len;
+        let mut end = s.len().min(avail);
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        self.slot[self.len..self.len + end].copy_from_slice(&s.as_bytes()[..end]);
+        self.len += end;
+        if end < s.len() {
+            // Stop formatting since the slot is full.
+            return Err(fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+/// Buffers the message of the syslog level on the current CPU.
+pub(crate) fn push(level: Option<u8>, args: fmt::Arguments) {
+    let irq_guard = trap::disable_local();
+    let buffer = BUFFERS.get_with(&irq_guard);
+
+    // The buffer is busy if the message is logged while formatting another message on the
+    // current CPU or if the buffer is being drained by another CPU.
+    let is_pushed = buffer
+        .try_lock()
+        .is_some_and(|mut buffer| buffer.push(level, args));
+    if !is_pushed {
+        NR_DROPPED.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // This must come after pushing the message. See `drain` for details.
+    HAS_PENDING.store(true, Ordering::SeqCst);
+}
+
+/// Returns whether there may be buffered messages that have not been printed.
+pub(crate) fn has_pending() -> bool {
+    HAS_PENDING.load(Ordering::SeqCst)
+}
+
+/// Prints all the buffered messages of all CPUs with `printer`, from the oldest to the newest.
+///
+/// The caller must hold the consoles so that there is at most one drainer at a time. After
+/// releasing the consoles, the caller should check [`has_pending`] again, because new messages
+/// might be buffered after draining but before releasing.
+pub(crate) fn drain(printer: &mut Printer) {
+    // The flag is cleared before draining, so a message that is buffered after the buffers are
+    // drained always sets the flag again.
+    HAS_PENDING.store(false, Ordering::SeqCst);
+
+    let nr_dropped = NR_DROPPED.swap(0, Ordering::Relaxed);
+    if nr_dropped > 0 {
+        printer.print(
+            None,
+            format_args!("[{} log messages dropped]\n", nr_dropped),
+        );
+    }
+
+    let mut msg = [0; SLOT_LEN];
+    loop {
+        // Busy buffers are skipped. Their messages will be drained later since the flag will be
+        // set again after pushing.
+        let oldest = all_cpus()
+            .filter_map(|cpu| {
+                let seq = BUFFERS.get_on_cpu(cpu).try_lock()?.front_seq()?;
+                Some((cpu, seq))
+            })
+            .min_by_key(|(_, seq)| *seq);
+        let Some((cpu, _)) = oldest else {
+            break;
+        };
+
+        // The message is copied out so that the buffer is not held while printing.
+        let Some((len, level)) = BUFFERS
+            .get_on_cpu(cpu)
+            .try_lock()
+            .and_then(|mut buffer| buffer.pop_front(&mut msg))
+        else {
+            continue;
+        };
+        // The slot is filled by `SlotWriter`, which only keeps whole UTF-8 characters.
+        let msg = core::str::from_utf8(&msg[..len]).unwrap_or_default();
+        printer.print(level, format_args!("{}", msg));
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn push_and_pop() {
+        let mut buffer = LogBuffer::new();
+        let mut msg = [0; SLOT_LEN];
+
+        assert!(buffer.push(None, format_args!("first\n")));
+        assert!(buffer.push(Some(4), format_args!("second\n")));
+        assert!(buffer.front_seq().unwrap() < buffer.seqs[1]);
+
+        assert_eq!(buffer.pop_front(&mut msg), Some((6, None)));
+        assert_eq!(&msg[..6], b"first\n");
+        assert_eq!(buffer.pop_front(&mut msg), Some((7, Some(4))));
+        assert_eq!(&msg[..7], b"second\n");
+        assert_eq!(buffer.pop_front(&mut msg), None);
+    }
+
+    #[ktest]
+    fn full_buffer() {
+        let mut buffer = LogBuffer::new();
+
+        for i in 0..NR_SLOTS {
+            assert!(buffer.push(None, format_args!("{}\n", i)));
+        }
+        assert!(!buffer.push(None, format_args!("dropped\n")));
+    }
+
+    #[ktest]
+    fn truncated_message() {
+        let mut buffer = LogBuffer::new();
+        let mut msg = [0; SLOT_LEN];
+
+        // The multi-byte characters must not be split.
+        let long = "é".repeat(SLOT_LEN);
+        assert!(buffer.push(None, format_args!("{}\n", long)));
+
+        let (len, _) = buffer.pop_front(&mut msg).unwrap();
+        assert_eq!(len, SLOT_LEN - 1);
+        let msg = core::str::from_utf8(&msg[..len]).unwrap();
+        assert!(msg.ends_with("é\n"));
+    }
+}
@@ -2,37 +2,114 @@
 
 //! `print` and `println` macros
 //!
-//! FIXME: It will print to all `virtio-console` devices, which is not a good choice.
+//! The messages are printed on the consoles selected by the `console=` kernel command-line
+//! arguments, or on all consoles if none is selected (see [`aster_console::ConsoleTable`]).
 //!
 
-use alloc::{collections::btree_map::BTreeMap, fmt, string::String, sync::Arc};
+use alloc::fmt;
 use core::fmt::Write;
 
-use aster_console::AnyConsoleDevice;
-use ostd::sync::{LocalIrqDisabled, SpinLockGuard};
+use aster_console::ConsoleTable;
+use ostd::{
+    console::early_print,
+    sync::{LocalIrqDisabled, SpinLockGuard},
+};
+
+use crate::buffer;
 
 /// Prints the formatted arguments to the standard output.
+///
+/// This function never spins on the consoles, so it can be called in the interrupt context. If
+/// the consoles are busy, the message is buffered and printed later by whoever holds them.
 pub fn _print(args: fmt::Arguments) {
-    // We must call `all_devices_lock` instead of `all_devices` here, as `all_devices` invokes the
-    // `clone` method of `String` and `Arc`, which may lead to a deadlock when there is low memory
-    // in the heap. (The heap allocator will log a message when memory is low.)
+    print_at_level(None, args);
+}
+
+/// Prints the formatted arguments on the consoles whose console log levels are greater than the
+/// syslog level, or on all the consoles if the level is `None`.
+///
+/// Like [`_print`], this function never spins on the consoles.
+pub fn print_at_level(level: Option<u8>, args: fmt::Arguments) {
+    // We must call `try_console_table_lock` instead of `all_devices` here, as `all_devices`
+    // invokes the `clone` method of `String` and `Arc`, which may lead to a deadlock when there is
+    // low memory in the heap. (The heap allocator will log a message when memory is low.)
     //
     // Also, holding the lock will prevent the logs from interleaving.
-    let devices = aster_console::all_devices_lock();
-
-    struct Printer<'a>(
-        SpinLockGuard<'a, BTreeMap<String, Arc<dyn AnyConsoleDevice>>, LocalIrqDisabled>,
-    );
-    impl Write for Printer<'_> {
-        fn write_str(&mut self, s: &str) -> fmt::Result {
-            self.0
-                .values()
-                .for_each(|console| console.send(s.as_bytes()));
-            Ok(())
+    let Some(table) = aster_console::try_console_table_lock() else {
+        buffer::push(level, args);
+        // The holder may have released the consoles before seeing the message.
+        flush();
+        return;
+    };
+
+    let mut printer = Printer(table);
+    // Print the buffered messages first, as they are older.
+    buffer::drain(&mut printer);
+    printer.print(level, args);
+    drop(printer);
+
+    flush();
+}
+
+/// Prints the buffered messages, if the consoles are not busy.
+fn flush() {
+    while buffer::has_pending() {
+        let Some(table) = aster_console::try_console_table_lock() else {
+            // The holder will print them.
+            return;
+        };
+        buffer::drain(&mut Printer(table));
+    }
+}
+
+pub(crate) struct Printer<'a>(SpinLockGuard<'a, ConsoleTable, LocalIrqDisabled>);
+
+impl Printer<'_> {
+    /// Prints the message on the consoles whose console log levels are greater than its level.
+    pub(crate) fn print(&mut self, level: Option<u8>, args: fmt::Arguments) {
+        if self.0.is_early() {
+            if is_printed(level, None) {
+                early_print(args);
+            }
+            return;
+        }
+
+        // Avoid formatting the message if no console prints it.
+        if !self
+            .0
+            .message_consoles()
+            .any(|(_, console_level)| is_printed(level, console_level))
+        {
+            return;
         }
+        let _ = ConsolesWriter {
+            table: &self.0,
+            level,
+        }
+        .write_fmt(args);
+    }
+}
+
+/// A writer that sends the formatted output to the consoles that print the message.
+struct ConsolesWriter<'a> {
+    table: &'a ConsoleTable,
+    level: Option<u8>,
+}
+
+impl Write for ConsolesWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.table
+            .message_consoles()
+            .filter(|(_, console_level)| is_printed(self.level, *console_level))
+            .for_each(|(console, _)| console.send(s.as_bytes()));
+        Ok(())
     }
+}
 
-    Printer(devices).write_fmt(args).unwrap();
+/// Returns whether the message of the syslog level is printed on the console with the console
+/// log level, where the global console log level is used if the console has no level.
+fn is_printed(level: Option<u8>, console_level: Option<u8>) -> bool {
+    level.is_none_or(|level| level < console_level.unwrap_or_else(crate::console_level))
 }
 
 /// Copied from Rust std: <https://github.com/rust-lang/rust/blob/master/library/std/src/macros.rs>
@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The console log level, which decides the messages that are printed on the consoles.
+//!
+//! As in Linux, a message is printed only if its syslog level is less than the console log
+//! level. The messages that are not printed are still kept as the records of the kernel log.
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// The minimum console log level, with which only the emergency messages are printed.
+pub const MIN_CONSOLE_LEVEL: u8 = 1;
+
+/// The maximum console log level, with which all messages are printed.
+pub const MAX_CONSOLE_LEVEL: u8 = 8;
+
+/// The default console log level.
+///
+/// Unlike Linux, where the default level hides the debug messages, all messages are printed by
+/// default. The logged messages are already filtered by `ostd.log_level`.
+pub const DEFAULT_CONSOLE_LEVEL: u8 = MAX_CONSOLE_LEVEL;
+
+static CONSOLE_LEVEL: AtomicU8 = AtomicU8::new(DEFAULT_CONSOLE_LEVEL);
+
+/// Returns the console log level.
+pub fn console_level() -> u8 {
+    CONSOLE_LEVEL.load(Ordering::Relaxed)
+}
+
+/// Sets the console log level.
+///
+/// The level is clamped between [`MIN_CONSOLE_LEVEL`] and [`MAX_CONSOLE_LEVEL`].
+pub fn set_console_level(level: u8) {
+    let level = level.clamp(MIN_CONSOLE_LEVEL, MAX_CONSOLE_LEVEL);
+    CONSOLE_LEVEL.store(level, Ordering::Relaxed);
+}
+
+/// Returns whether the messages with the syslog level should be printed on the consoles.
+pub fn is_printed_on_console(level: u8) -> bool {
+    level < console_level()
+}
@@ -10,7 +10,18 @@
 //! printed in their entirety without being mixed with messages generated
 //! concurrently on other cores.
 //!
-//! IRQs are disabled while printing. So do not print long log messages.
+//! Printing never waits for the consoles, so it can be used in the interrupt
+//! context. If the consoles are busy, messages are buffered per CPU and printed
+//! later in order. Buffered messages may be truncated, or dropped if the buffer
+//! is full.
+//!
+//! IRQs are disabled while printing. So do not print long log messages. For
+//! frequent messages, use the rate-limited macros such as [`warn_ratelimited`].
+//!
+//! The logged messages are also kept as the records of the kernel log, which can be read
+//! with [`read_record`]. Only the messages below the console log level (see
+//! [`set_console_level`]) are printed, but all of them are recorded. A console
+//! selected by `console=<name>,loglevel:<level>` has its own console log level.
 #![no_std]
 #![deny(unsafe_code)]
 
@@ -19,9 +30,24 @@ extern crate alloc;
 use component::{init_component, ComponentInitError};
 
 mod aster_logger;
+mod buffer;
 mod console;
+mod level;
+mod ratelimit;
+mod record;
 
-pub use console::_print;
+pub use console::{_print, print_at_level};
+#[doc(hidden)]
+pub use level::{
+    console_level, is_printed_on_console, set_console_level, DEFAULT_CONSOLE_LEVEL,
+    MAX_CONSOLE_LEVEL, MIN_CONSOLE_LEVEL,
+};
+pub use log as __log;
+pub use ratelimit::{RateLimit, DEFAULT_RATELIMIT_BURST, DEFAULT_RATELIMIT_INTERVAL};
+pub use record::{
+    append_record, clear_records, first_record_seq, first_uncleared_record_seq, next_record_seq,
+    read_record, RecordInfo, LOG_BUF_LEN, MAX_RECORD_LEN,
+};
 
 #[init_component]
 fn init() -> Result<(), ComponentInitError> {
@@ -0,0 +1,160 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Rate limiting of log messages.
+//!
+//! The semantics follow `printk_ratelimited` of Linux: at most `burst` messages are printed in
+//! each interval, and the number of the suppressed messages is reported with the next message
+//! that is printed.
+
+use core::time::Duration;
+
+use ostd::{
+    sync::{LocalIrqDisabled, SpinLock},
+    timer::Jiffies,
+};
+
+/// The default interval of [`RateLimit`], which is the same as Linux.
+pub const DEFAULT_RATELIMIT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The default burst of [`RateLimit`], which is the same as Linux.
+pub const DEFAULT_RATELIMIT_BURST: usize = 10;
+
+/// A limit of how often something, usually printing a log message, can happen.
+pub struct RateLimit {
+    interval: Duration,
+    burst: usize,
+    state: SpinLock<RateLimitState, LocalIrqDisabled>,
+}
+
+struct RateLimitState {
+    /// The jiffies when the current interval begins, or `None` if nothing has happened.
+    begin: Option<u64>,
+    /// The number of the allowed events in the current interval.
+    nr_allowed: usize,
+    /// The number of the suppressed events that have not been reported.
+    nr_suppressed: usize,
+}
+
+impl RateLimit {
+    /// Creates a limit that allows at most `burst` events in each `interval`.
+    pub const fn new(interval: Duration, burst: usize) -> Self {
+        Self {
+            interval,
+            burst,
+            state: SpinLock::new(RateLimitState {
+                begin: None,
+                nr_allowed: 0,
+                nr_suppressed: 0,
+            }),
+        }
+    }
+
+    /// Creates a limit with the default interval and burst.
+    pub const fn new_default() -> Self {
+        Self::new(DEFAULT_RATELIMIT_INTERVAL, DEFAULT_RATELIMIT_BURST)
+    }
+
+    /// Checks whether an event is allowed now.
+    ///
+    /// If it is allowed, this method returns the number of the suppressed events that should be
+    /// reported. Otherwise, the event is counted as suppressed and `None` is returned.
+    pub fn check(&self) -> Option<usize> {
+        // Like Linux, the event is suppressed without being counted if the limit is being checked
+        // concurrently, e.g., by the interrupted code on the current CPU.
+        let mut state = self.state.try_lock()?;
+
+        let now = Jiffies::elapsed().as_u64();
+        let begin = *state.begin.get_or_insert(now);
+        if Jiffies::new(now.saturating_sub(begin)).as_duration() >= self.interval {
+            state.begin = Some(now);
+            state.nr_allowed = 0;
+        }
+
+        if state.nr_allowed >= self.burst {
+            state.nr_suppressed += 1;
+            return None;
+        }
+
+        state.nr_allowed += 1;
+        Some(core::mem::take(&mut state.nr_suppressed))
+    }
+}
+
+/// Logs a message with the given level if the rate limit of the call site is not exceeded.
+///
+/// The rate limit has the default interval and burst. See also [`RateLimit`].
+#[macro_export]
+macro_rules! log_ratelimited {
+    ($level:expr, $($arg:tt)+) => {{
+        static RATE_LIMIT: $crate::RateLimit = $crate::RateLimit::new_default();
+
+        let level = $level;
+        if $crate::__log::log_enabled!(level) {
+            if let Some(nr_suppressed) = RATE_LIMIT.check() {
+                if nr_suppressed > 0 {
+                    $crate::__log::log!(level, "{} messages suppressed", nr_suppressed);
+                }
+                $crate::__log::log!(level, $($arg)+);
+            }
+        }
+    }};
+}
+
+/// Logs an error message with the rate limit of the call site. See [`log_ratelimited`].
+#[macro_export]
+macro_rules! error_ratelimited {
+    ($($arg:tt)+) => {
+        $crate::log_ratelimited!($crate::__log::Level::Error, $($arg)+)
+    };
+}
+
+/// Logs a warning message with the rate limit of the call site. See [`log_ratelimited`].
+#[macro_export]
+macro_rules! warn_ratelimited {
+    ($($arg:tt)+) => {
+        $crate::log_ratelimited!($crate::__log::Level::Warn, $($arg)+)
+    };
+}
+
+/// Logs an info message with the rate limit of the call site. See [`log_ratelimited`].
+#[macro_export]
+macro_rules! info_ratelimited {
+    ($($arg:tt)+) => {
+        $crate::log_ratelimited!($crate::__log::Level::Info, $($arg)+)
+    };
+}
+
+/// Logs a debug message with the rate limit of the call site. See [`log_ratelimited`].
+#[macro_export]
+macro_rules! debug_ratelimited {
+    ($($arg:tt)+) => {
+        $crate::log_ratelimited!($crate::__log::Level::Debug, $($arg)+)
+    };
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn burst_and_suppressed() {
+        let limit = RateLimit::new(Duration::from_secs(3600), 2);
+
+        assert_eq!(limit.check(), Some(0));
+        assert_eq!(limit.check(), Some(0));
+        assert_eq!(limit.check(), None);
+        assert_eq!(limit.check(), None);
+    }
+
+    #[ktest]
+    fn zero_interval() {
+        let limit = RateLimit::new(Duration::ZERO, 1);
+
+        // Each check begins a new interval.
+        for _ in 0..4 {
+            assert_eq!(limit.check(), Some(0));
+        }
+    }
+}
@@ -0,0 +1,307 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The records of the kernel log, which can be read from the user space.
+//!
+//! Every logged message and every message written by the user space (e.g., to `/dev/kmsg`) is
+//! kept as a record in a ring buffer. Each record has a sequence number, a syslog priority, and
+//! a timestamp. When the buffer is full, the oldest records are overwritten, so a reader that
+//! falls behind can tell the lost records from the gap in the sequence numbers.
+//!
+//! Recording never allocates memory, so it is safe in the interrupt context. The messages are
+//! formatted in per-CPU buffers first, so that the global buffer is only locked for copying.
+
+use core::{
+    fmt::{self, Write},
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use ostd::{
+    cpu_local,
+    sync::{LocalIrqDisabled, SpinLock},
+    timer::Jiffies,
+    trap,
+};
+
+/// The maximum length of the text of a record in bytes, which is the same as Linux.
+///
+/// Longer texts are truncated.
+pub const MAX_RECORD_LEN: usize = 1024;
+
+/// The size of the ring buffer in bytes.
+pub const LOG_BUF_LEN: usize = 64 * 1024;
+
+/// The length of the header of a record in the ring buffer.
+///
+/// The header consists of the timestamp in microseconds (8 bytes), the length of the text
+/// (2 bytes), and the priority (1 byte).
+const HEADER_LEN: usize = 11;
+
+static RING: SpinLock<LogRing, LocalIrqDisabled> = SpinLock::new(LogRing::new());
+
+cpu_local! {
+    static SCRATCH: SpinLock<[u8; MAX_RECORD_LEN], LocalIrqDisabled> =
+        SpinLock::new([0; MAX_RECORD_LEN]);
+}
+
+/// The sequence number of the next record, which can be read without locking the buffer.
+static NEXT_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// The sequence number of the first record that is not cleared.
+static CLEAR_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// The metadata of a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordInfo {
+    /// The sequence number.
+    pub seq: u64,
+    /// The syslog priority, i.e., the facility shifted left by 3 and ORed with the level.
+    pub priority: u8,
+    /// The time since the system boots.
+    pub timestamp: Duration,
+    /// The length of the text in bytes.
+    pub len: usize,
+}
+
+/// Converts the level of a logged message to a syslog level.
+pub(crate) fn syslog_level(level: log::Level) -> u8 {
+    match level {
+        log::Level::Error => 3,
+        log::Level::Warn => 4,
+        log::Level::Info => 6,
+        log::Level::Debug | log::Level::Trace => 7,
+    }
+}
+
+/// Records a logged message of the kernel.
+///
+/// The message is dropped if it is logged while formatting another message on the current CPU.
+pub(crate) fn record_log(level: log::Level, args: fmt::Arguments, timestamp: Duration) {
+    let irq_guard = trap::disable_local();
+    let Some(mut scratch) = SCRATCH.get_with(&irq_guard).try_lock() else {
+        return;
+    };
+
+    let mut writer = TextWriter {
+        buf: &mut scratch,
+        len: 0,
+    };
+    let _ = writer.write_fmt(args);
+    let len = writer.len;
+
+    // The kernel facility is zero.
+    RING.lock()
+        .push(syslog_level(level), timestamp, &scratch[..len]);
+}
+
+/// Appends a record with the priority and the text.
+///
+/// The text is truncated to [`MAX_RECORD_LEN`] bytes.
+pub fn append_record(priority: u8, text: &[u8]) {
+    let timestamp = Jiffies::elapsed().as_duration();
+    let len = text.len().min(MAX_RECORD_LEN);
+    RING.lock().push(priority, timestamp, &text[..len]);
+}
+
+/// Reads the oldest record whose sequence number is not less than `seq`.
+///
+/// The text is copied to `buf`, which should hold at least [`MAX_RECORD_LEN`] bytes. Otherwise,
+/// the text is truncated.
+///
+/// This function returns `None` if there is no such record. If the returned sequence number is
+/// greater than `seq`, the records in between have been overwritten.
+pub fn read_record(seq: u64, buf: &mut [u8]) -> Option<RecordInfo> {
+    RING.lock().read(seq, buf)
+}
+
+/// Returns the sequence number of the oldest record that is still kept.
+pub fn first_record_seq() -> u64 {
+    RING.lock().first_seq
+}
+
+/// Returns the sequence number of the oldest record that is neither cleared nor overwritten.
+pub fn first_uncleared_record_seq() -> u64 {
+    first_record_seq().max(CLEAR_SEQ.load(Ordering::Relaxed))
+}
+
+/// Clears all the records that have been added.
+///
+/// The records are still kept in the buffer and can be read with [`read_record`]. Clearing only
+/// affects [`first_uncleared_record_seq`], which the readers of the kernel log start with.
+pub fn clear_records() {
+    CLEAR_SEQ.fetch_max(next_record_seq(), Ordering::Relaxed);
+}
+
+/// Returns the sequence number of the next record.
+///
+/// A change of the returned value means that new records have been added.
+pub fn next_record_seq() -> u64 {
+    NEXT_SEQ.load(Ordering::Acquire)
+}
+
+/// A ring buffer of variable-length records.
+struct LogRing<const N: usize = LOG_BUF_LEN> {
+    data: [u8; N],
+    /// The offset of the oldest record.
+    head: usize,
+    /// The number of the bytes used by the records.
+    used: usize,
+    /// The sequence number of the oldest record.
+    first_seq: u64,
+    /// The sequence number of the next record.
+    next_seq: u64,
+}
+
+impl<const N: usize> LogRing<N> {
+    const fn new() -> Self {
+        Self {
+            data: [0; N],
+            head: 0,
+            used: 0,
+            first_seq: 0,
+            next_seq: 0,
+        }
+    }
+
+    fn push(&mut self, priority: u8, timestamp: Duration, text: &[u8]) {
+        let size = HEADER_LEN + text.len();
+        while N - self.used < size {
+            self.pop_front();
+        }
+
+        let mut header = [0; HEADER_LEN];
+        header[..8].copy_from_slice(&(timestamp.as_micros() as u64).to_le_bytes());
+        header[8..10].copy_from_slice(&(text.len() as u16).to_le_bytes());
+        header[10] = priority;
+
+        let offset = (self.head + self.used) % N;
+        self.copy_in(offset, &header);
+        self.copy_in((offset + HEADER_LEN) % N, text);
+        self.used += size;
+        self.next_seq += 1;
+        NEXT_SEQ.store(self.next_seq, Ordering::Release);
+    }
+
+    fn pop_front(&mut self) {
+        let (_, len, _) = self.header_at(self.head);
+        let size = HEADER_LEN + len;
+        self.head = (self.head + size) % N;
+        self.used -= size;
+        self.first_seq += 1;
+    }
+
+    fn read(&self, seq: u64, buf: &mut [u8]) -> Option<RecordInfo> {
+        if seq >= self.next_seq {
+            return None;
+        }
+
+        let seq = seq.max(self.first_seq);
+        let mut offset = self.head;
+        for _ in self.first_seq..seq {
+            let (_, len, _) = self.header_at(offset);
+            offset = (offset + HEADER_LEN + len) % N;
+        }
+
+        let (timestamp_us, len, priority) = self.header_at(offset);
+        let len = len.min(buf.len());
+        self.copy_out((offset + HEADER_LEN) % N, &mut buf[..len]);
+
+        Some(RecordInfo {
+            seq,
+            priority,
+            timestamp: Duration::from_micros(timestamp_us),
+            len,
+        })
+    }
+
+    /// Returns the timestamp in microseconds, the length, and the priority of the record at
+    /// the offset.
+    fn header_at(&self, offset: usize) -> (u64, usize, u8) {
+        let mut header = [0; HEADER_LEN];
+        self.copy_out(offset, &mut header);
+
+        let timestamp_us = u64::from_le_bytes(header[..8].try_into().unwrap());
+        let len = u16::from_le_bytes(header[8..10].try_into().unwrap()) as usize;
+        (timestamp_us, len, header[10])
+    }
+
+    fn copy_in(&mut self, offset: usize, bytes: &[u8]) {
+        let first = bytes.len().min(N - offset);
+        self.data[offset..offset + first].copy_from_slice(&bytes[..first]);
+        self.data[..bytes.len() - first].copy_from_slice(&bytes[first..]);
+    }
+
+    fn copy_out(&self, offset: usize, bytes: &mut [u8]) {
+        let first = bytes.len().min(N - offset);
+        bytes[..first].copy_from_slice(&self.data[offset..offset + first]);
+        let len = bytes.len();
+        bytes[first..].copy_from_slice(&self.data[..len - first]);
+    }
+}
+
+/// A writer that fills a buffer and truncates the excessive output.
+struct TextWriter<'a> {
+    buf: &'a mut [u8; MAX_RECORD_LEN],
+    len: usize,
+}
+
+impl Write for TextWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = s.len().min(MAX_RECORD_LEN - self.len);
+        self.buf[self.len..self.len + end].copy_from_slice(&s.as_bytes()[..end]);
+        self.len += end;
+        if end < s.len() {
+            // Stop formatting since the buffer is full.
+            return Err(fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    const TEST_BUF_LEN: usize = 4096;
+
+    #[ktest]
+    fn push_and_read() {
+        let mut ring = LogRing::<TEST_BUF_LEN>::new();
+        let mut buf = [0; MAX_RECORD_LEN];
+
+        ring.push(6, Duration::from_micros(1), b"first");
+        ring.push(12, Duration::from_micros(2), b"second");
+
+        let info = ring.read(0, &mut buf).unwrap();
+        assert_eq!((info.seq, info.priority, info.len), (0, 6, 5));
+        assert_eq!(&buf[..5], b"first");
+        let info = ring.read(1, &mut buf).unwrap();
+        assert_eq!((info.seq, info.priority, info.len), (1, 12, 6));
+        assert_eq!(info.timestamp, Duration::from_micros(2));
+        assert_eq!(&buf[..6], b"second");
+        assert!(ring.read(2, &mut buf).is_none());
+    }
+
+    #[ktest]
+    fn overwritten_records() {
+        let mut ring = LogRing::<TEST_BUF_LEN>::new();
+        let mut buf = [0; MAX_RECORD_LEN];
+        let text = [b'x'; MAX_RECORD_LEN];
+
+        // The records wrap around the end of the buffer.
+        let nr_records = TEST_BUF_LEN / (HEADER_LEN + MAX_RECORD_LEN) * 3;
+        for _ in 0..nr_records {
+            ring.push(6, Duration::ZERO, &text);
+        }
+
+        assert!(ring.first_seq > 0);
+        let info = ring.read(0, &mut buf).unwrap();
+        assert_eq!(info.seq, ring.first_seq);
+        assert_eq!(info.len, MAX_RECORD_LEN);
+        let info = ring.read(nr_records as u64 - 1, &mut buf).unwrap();
+        assert_eq!(info.len, MAX_RECORD_LEN);
+        assert_eq!(buf, text);
+    }
+}
@@ -161,6 +161,7 @@ impl<D: BlockSet + 'static> aster_block::BlockDevice for MlsDisk<D> {
         BlockDeviceMeta {
             max_nr_segments_per_bio: usize::MAX,
             nr_sectors: (BLOCK_SIZE / SECTOR_SIZE) * self.total_blocks(),
+            max_nr_sectors_per_discard: 0,
         }
     }
 }
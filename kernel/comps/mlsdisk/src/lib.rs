@@ -185,6 +185,7 @@ mod test {
             BlockDeviceMeta {
                 max_nr_segments_per_bio: usize::MAX,
                 nr_sectors: self.blocks.size() / SECTOR_SIZE,
+                max_nr_sectors_per_discard: usize::MAX,
             }
         }
     }
@@ -10,7 +10,7 @@ use alloc::boxed::Box;
 use core::sync::atomic::{AtomicU8, Ordering};
 
 use component::{init_component, ComponentInitError};
-use lock::is_softirq_enabled;
+use lock::{disable_local_bottom_half, is_softirq_enabled};
 use ostd::{
     cpu_local_cell,
     trap::{disable_local, register_bottom_half_handler, DisabledLocalIrqGuard},
@@ -123,6 +123,34 @@ fn init() -> Result<(), ComponentInitError> {
 
 static ENABLED_MASK: AtomicU8 = AtomicU8::new(0);
 
+static DAEMON_WAKER: Once<fn()> = Once::new();
+
+/// Registers the function that wakes up the softirq daemon of the current CPU.
+///
+/// The softirqs that are still pending after being processed for several
+/// rounds in the bottom half are deferred to the daemon, which runs in the
+/// task context, so as to bound the latency of the interrupted tasks. The
+/// function is called with the local IRQs disabled.
+pub fn register_daemon_waker(waker: fn()) {
+    DAEMON_WAKER.call_once(|| waker);
+}
+
+/// Returns whether any enabled softirq is pending on the current CPU.
+pub fn has_pending() -> bool {
+    PENDING_MASK.load() & ENABLED_MASK.load(Ordering::Acquire) != 0
+}
+
+/// Processes the pending softirqs of the current CPU in the task context.
+///
+/// This is meant to be called by the softirq daemon, which should be bound to
+/// the CPU.
+pub fn process_pending_in_task() {
+    let bh_guard = disable_local_bottom_half();
+    // Like what happens when any other task enables the bottom half, the
+    // pending softirqs are processed when the guard is dropped.
+    drop(bh_guard);
+}
+
 cpu_local_cell! {
     static PENDING_MASK: u8 = 0;
 }
@@ -165,7 +193,11 @@ fn process_all_pending(mut irq_guard: DisabledLocalIrqGuard) -> DisabledLocalIrq
         irq_guard = disable_local();
     }
 
-    // TODO: Wake up ksoftirqd if some softirqs are still pending.
+    if has_pending() {
+        if let Some(wake_daemon) = DAEMON_WAKER.get() {
+            wake_daemon();
+        }
+    }
 
     irq_guard
 }
@@ -58,7 +58,7 @@ impl Drop for DisableLocalBottomHalfGuard {
 }
 
 #[must_use]
-fn disable_local_bottom_half() -> DisableLocalBottomHalfGuard {
+pub(super) fn disable_local_bottom_half() -> DisableLocalBottomHalfGuard {
     // When disabling softirq, we must also disable preemption
     // to avoid the task to be scheduled to other CPUs.
     let preempt = disable_preempt();
@@ -1,28 +1,34 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use ostd::{arch::timer::GOLDFISH_IO_MEM, mm::VmIoOnce};
 use chrono::{DateTime, Datelike, Timelike};
+use ostd::{arch::timer::GOLDFISH_IO_MEM, io_registers};
 
-use crate::{SystemTime, rtc::Driver};
+use crate::{rtc::Driver, SystemTime};
 
-pub struct RtcGoldfish;
+io_registers! {
+    struct GoldfishRtcRegisters {
+        time_low: ReadOnly<u32> @ 0x00,
+        time_high: ReadOnly<u32> @ 0x04,
+    }
+}
+
+pub struct RtcGoldfish {
+    regs: GoldfishRtcRegisters,
+}
 
 impl Driver for RtcGoldfish {
     fn try_new() -> Option<RtcGoldfish> {
-        GOLDFISH_IO_MEM.get()?;
-        Some(RtcGoldfish)
+        let io_mem = GOLDFISH_IO_MEM.get()?.clone();
+        Some(RtcGoldfish {
+            regs: GoldfishRtcRegisters::new(io_mem),
+        })
     }
 
     fn read_rtc(&self) -> SystemTime {
-        const TIME_LOW: usize = 0;
-        const TIME_HIGH: usize = 4;
-
-        let io_mem = GOLDFISH_IO_MEM.get().unwrap();
-
-        let mut last_time_high = io_mem.read_once(TIME_HIGH).unwrap();
+        let mut last_time_high = self.regs.time_high().read();
         let timestamp = loop {
-            let time_low: u32 = io_mem.read_once(TIME_LOW).unwrap();
-            let time_high: u32 = io_mem.read_once(TIME_HIGH).unwrap();
+            let time_low = self.regs.time_low().read();
+            let time_high = self.regs.time_high().read();
             if last_time_high == time_high {
                 break ((time_high as u64) << 32) | time_low as u64;
             }
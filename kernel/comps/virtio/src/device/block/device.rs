@@ -3,12 +3,19 @@
 use alloc::{
     boxed::Box,
     collections::BTreeMap,
+    format,
     string::{String, ToString},
     sync::Arc,
     vec,
     vec::Vec,
 };
-use core::{fmt::Debug, hint::spin_loop, mem::size_of};
+use core::{
+    fmt::Debug,
+    hint::spin_loop,
+    iter,
+    mem::size_of,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use aster_block::{
     bio::{bio_segment_pool_init, BioEnqueueError, BioStatus, BioType, SubmittedBio},
@@ -19,7 +26,7 @@ use id_alloc::IdAlloc;
 use log::{debug, info};
 use ostd::{
     mm::{DmaDirection, DmaStream, DmaStreamSlice, FrameAllocOptions, VmIo},
-    sync::SpinLock,
+    sync::{SpinLock, WaitQueue},
     trap::TrapFrame,
     Pod,
 };
@@ -28,8 +35,9 @@ use super::{BlockFeatures, VirtioBlockConfig, VirtioBlockFeature};
 use crate::{
     device::{
         block::{ReqType, RespStatus},
-        VirtioDeviceError,
+        VirtioDeviceError, VirtioDeviceType,
     },
+    driver::{VirtioDevice, VirtioDriver},
     queue::VirtQueue,
     transport::{ConfigManager, VirtioTransport},
 };
@@ -41,9 +49,19 @@ pub struct BlockDevice {
     queue: BioRequestSingleQueue,
 }
 
-impl BlockDevice {
+impl VirtioDriver for BlockDevice {
+    const DEVICE_TYPE: VirtioDeviceType = VirtioDeviceType::Block;
+    const SUPPORTS_EVENT_IDX: bool = true;
+
+    /// Negotiate features for the device specified bits 0~23
+    fn negotiate_features(features: u64) -> u64 {
+        let mut support_features = BlockFeatures::from_bits_truncate(features);
+        support_features.remove(BlockFeatures::MQ);
+        support_features.bits
+    }
+
     /// Creates a new VirtIO-Block driver and registers it.
-    pub(crate) fn init(transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
+    fn init(transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
         let is_legacy = transport.is_legacy_version();
         let device = DeviceInner::init(transport)?;
         let device_id = if is_legacy {
@@ -52,14 +70,20 @@ impl BlockDevice {
         } else {
             device.request_device_id()
         };
+        // Like Linux, the devices without a serial ID are named `vda`, `vdb`, etc.
+        let device_id = if device_id.is_empty() {
+            static NEXT_INDEX: AtomicUsize = AtomicUsize::new(0);
+            let index = NEXT_INDEX.fetch_add(1, Ordering::Relaxed);
+            format!("vd{}", (b'a' + (index % 26) as u8) as char)
+        } else {
+            device_id
+        };
 
         let block_device = Arc::new(Self {
-            device,
-            // Each bio request includes an additional 1 request and 1 response descriptor,
-            // therefore this upper bound is set to (QUEUE_SIZE - 2).
             queue: BioRequestSingleQueue::with_max_nr_segments_per_bio(
-                (DeviceInner::QUEUE_SIZE - 2) as usize,
+                device.max_nr_segments_per_request,
             ),
+            device,
         });
 
         aster_block::register_device(device_id, block_device);
@@ -67,25 +91,21 @@ impl BlockDevice {
         bio_segment_pool_init();
         Ok(())
     }
+}
 
-    /// Dequeues a `BioRequest` from the software staging queue and
-    /// processes the request.
+impl BlockDevice {
+    /// Dequeues `BioRequest`s from the software staging queue and
+    /// processes the requests.
+    ///
+    /// This method waits for at least one request. The requests that have
+    /// been staged when it returns are submitted to the device in a batch.
     pub fn handle_requests(&self) {
         let request = self.queue.dequeue();
-        info!("Handle Request: {:?}", request);
-        match request.type_() {
-            BioType::Read => self.device.read(request),
-            BioType::Write => self.device.write(request),
-            BioType::Flush => self.device.flush(request),
-            BioType::Discard => todo!(),
-        }
-    }
-
-    /// Negotiate features for the device specified bits 0~23
-    pub(crate) fn negotiate_features(features: u64) -> u64 {
-        let mut support_features = BlockFeatures::from_bits_truncate(features);
-        support_features.remove(BlockFeatures::MQ);
-        support_features.bits
+        let requests = iter::once(request)
+            .chain(iter::from_fn(|| self.queue.try_dequeue()))
+            .take(DeviceInner::QUEUE_SIZE as usize)
+            .inspect(|request| debug!("Handle Request: {:?}", request));
+        self.device.submit(requests);
     }
 }
 
@@ -98,11 +118,11 @@ impl aster_block::BlockDevice for BlockDevice {
         BlockDeviceMeta {
             max_nr_segments_per_bio: self.queue.max_nr_segments_per_bio(),
             nr_sectors: self.device.config_manager.capacity_sectors(),
+            max_nr_sectors_per_discard: self.device.max_nr_sectors_per_discard,
         }
     }
 }
 
-#[derive(Debug)]
 struct DeviceInner {
     config_manager: ConfigManager<VirtioBlockConfig>,
     features: VirtioBlockFeature,
@@ -110,12 +130,29 @@ struct DeviceInner {
     transport: SpinLock<Box<dyn VirtioTransport>>,
     block_requests: DmaStream,
     block_responses: DmaStream,
+    /// The discard segments, one for each request.
+    block_discards: DmaStream,
     id_allocator: SpinLock<IdAlloc>,
     submitted_requests: SpinLock<BTreeMap<u16, SubmittedRequest>>,
+    /// The wait queue for the free descriptors in the virtqueue.
+    free_desc_wait_queue: WaitQueue,
+    /// The maximum number of data segments in a request.
+    max_nr_segments_per_request: usize,
+    /// The maximum number of sectors in a discard request, or zero if the
+    /// device does not support discarding sectors.
+    max_nr_sectors_per_discard: usize,
+}
+
+impl VirtioDevice for DeviceInner {
+    fn with_transport(&self, f: &mut dyn FnMut(&mut dyn VirtioTransport)) {
+        f(self.transport.disable_irq().lock().as_mut());
+    }
 }
 
 impl DeviceInner {
     const QUEUE_SIZE: u16 = 64;
+    /// The maximum number of descriptors in an indirect descriptor table.
+    const MAX_INDIRECT_DESCS: u16 = 128;
 
     /// Creates and inits the device.
     pub fn init(mut transport: Box<dyn VirtioTransport>) -> Result<Arc<Self>, VirtioDeviceError> {
@@ -138,8 +175,21 @@ impl DeviceInner {
             );
         }
         let features = VirtioBlockFeature::new(transport.as_ref());
-        let queue = VirtQueue::new(0, Self::QUEUE_SIZE, transport.as_mut())
+        let mut queue = VirtQueue::new(0, Self::QUEUE_SIZE, transport.as_mut())
             .expect("create virtqueue failed");
+        if features.support_indirect_desc {
+            queue.enable_indirect_desc(Self::MAX_INDIRECT_DESCS);
+        }
+        if features.support_event_idx {
+            queue.enable_event_idx();
+        }
+
+        // Each request includes an additional request header and a response status.
+        let mut max_nr_segments_per_request = queue.max_descs_per_buf() - 2;
+        if features.support_seg_max {
+            let seg_max = config_manager.seg_max() as usize;
+            max_nr_segments_per_request = max_nr_segments_per_request.min(seg_max);
+        }
         let block_requests = {
             let segment = FrameAllocOptions::new().alloc_segment(1).unwrap();
             DmaStream::map(segment.into(), DmaDirection::Bidirectional, false).unwrap()
@@ -150,6 +200,18 @@ impl DeviceInner {
             DmaStream::map(segment.into(), DmaDirection::Bidirectional, false).unwrap()
         };
         assert!(Self::QUEUE_SIZE as usize * RESP_SIZE <= block_responses.nbytes());
+        let block_discards = {
+            let segment = FrameAllocOptions::new().alloc_segment(1).unwrap();
+            DmaStream::map(segment.into(), DmaDirection::ToDevice, false).unwrap()
+        };
+        assert!(Self::QUEUE_SIZE as usize * DISCARD_SIZE <= block_discards.nbytes());
+
+        // Each discard request has only one discard segment.
+        let max_nr_sectors_per_discard = if features.support_discard {
+            config_manager.max_discard_sectors() as usize
+        } else {
+            0
+        };
 
         let device = Arc::new(Self {
             config_manager,
@@ -158,8 +220,12 @@ impl DeviceInner {
             transport: SpinLock::new(transport),
             block_requests,
             block_responses,
+            block_discards,
             id_allocator: SpinLock::new(IdAlloc::with_capacity(Self::QUEUE_SIZE as usize)),
             submitted_requests: SpinLock::new(BTreeMap::new()),
+            free_desc_wait_queue: WaitQueue::new(),
+            max_nr_segments_per_request,
+            max_nr_sectors_per_discard,
         });
 
         let cloned_device = device.clone();
@@ -180,15 +246,16 @@ impl DeviceInner {
             transport
                 .register_queue_callback(0, Box::new(handle_irq), false)
                 .unwrap();
-            transport.finish_init();
         }
 
+        crate::driver::register_device(device.clone());
+
         Ok(device)
     }
 
     /// Handles the irq issued from the device
     fn handle_irq(&self) {
-        info!("Virtio block device handle irq");
+        debug!("Virtio block device handle irq");
         // When we enter the IRQs handling function,
         // IRQs have already been disabled,
         // so there is no need to call `disable_irq`.
@@ -197,7 +264,7 @@ impl DeviceInner {
             let complete_request = {
                 let mut queue = self.queue.lock();
                 let Ok((token, _)) = queue.pop_used() else {
-                    return;
+                    break;
                 };
                 self.submitted_requests.lock().remove(&token).unwrap()
             };
@@ -208,14 +275,16 @@ impl DeviceInner {
             resp_slice.sync().unwrap();
             let resp: BlockResp = resp_slice.read_val(0).unwrap();
             self.id_allocator.lock().free(id);
-            match RespStatus::try_from(resp.status).unwrap() {
-                RespStatus::Ok => {}
-                // FIXME: Return an error instead of triggering a kernel panic
-                _ => panic!("io error in block device"),
+            let status = match RespStatus::try_from(resp.status) {
+                Ok(RespStatus::Ok) => BioStatus::Complete,
+                Ok(RespStatus::Unsupported) => BioStatus::NotSupported,
+                _ => BioStatus::IoError,
             };
 
             // Synchronize DMA mapping if read from the device
-            if let BioType::Read = complete_request.bio_request.type_() {
+            if status == BioStatus::Complete
+                && complete_request.bio_request.type_() == BioType::Read
+            {
                 complete_request
                     .bio_request
                     .bios()
@@ -228,10 +297,11 @@ impl DeviceInner {
             }
 
             // Completes the bio request
-            complete_request.bio_request.bios().for_each(|bio| {
-                bio.complete(BioStatus::Complete);
-            });
+            complete_bio_request(&complete_request.bio_request, status);
         }
+
+        // Wakes up the submitter waiting for the free descriptors
+        self.free_desc_wait_queue.wake_all();
     }
 
     fn handle_config_change(&self) {
@@ -304,80 +374,102 @@ impl DeviceInner {
         String::from_utf8(device_id).unwrap()
     }
 
-    /// Reads data from the device, this function is non-blocking.
-    fn read(&self, bio_request: BioRequest) {
-        let id = self.id_allocator.disable_irq().lock().alloc().unwrap();
-        let req_slice = {
-            let req_slice =
-                DmaStreamSlice::new(self.block_requests.clone(), id * REQ_SIZE, REQ_SIZE);
-            let req = BlockReq {
-                type_: ReqType::In as _,
-                reserved: 0,
-                sector: bio_request.sid_range().start.to_raw(),
-            };
-            req_slice.write_val(0, &req).unwrap();
-            req_slice.sync().unwrap();
-            req_slice
-        };
+    /// Submits the requests to the device, which are completed asynchronously
+    /// in the IRQ handler.
+    ///
+    /// The device is notified only once after all the requests are added to
+    /// the virtqueue, unless the virtqueue becomes full in the middle.
+    fn submit(&self, bio_requests: impl Iterator<Item = BioRequest>) {
+        let mut has_added = false;
+        for bio_request in bio_requests {
+            match bio_request.type_() {
+                BioType::Read | BioType::Write => {}
+                // The flush is a no-op if the device has no volatile write cache.
+                BioType::Flush if !self.features.support_flush => {
+                    complete_bio_request(&bio_request, BioStatus::Complete);
+                    continue;
+                }
+                BioType::Flush => {}
+                // The discard requests are never merged (see `BioRequest::can_merge`), so they
+                // are within the limit if they are split by `discard_blocks_async`.
+                BioType::Discard
+                    if self.max_nr_sectors_per_discard == 0
+                        || bio_request.num_sectors() > self.max_nr_sectors_per_discard =>
+                {
+                    complete_bio_request(&bio_request, BioStatus::NotSupported);
+                    continue;
+                }
+                BioType::Discard => {}
+            }
 
-        let resp_slice = {
-            let resp_slice =
-                DmaStreamSlice::new(self.block_responses.clone(), id * RESP_SIZE, RESP_SIZE);
-            resp_slice.write_val(0, &BlockResp::default()).unwrap();
-            resp_slice
-        };
+            self.add_request(bio_request);
+            has_added = true;
+        }
+        if !has_added {
+            return;
+        }
 
-        let outputs = {
-            let mut outputs: Vec<&DmaStreamSlice<_>> =
-                Vec::with_capacity(bio_request.num_segments() + 1);
-            let dma_slices_iter = bio_request.bios().flat_map(|bio| {
-                bio.segments()
-                    .iter()
-                    .map(|segment| segment.inner_dma_slice())
-            });
-            outputs.extend(dma_slices_iter);
-            outputs.push(&resp_slice);
-            outputs
-        };
+        let mut queue = self.queue.disable_irq().lock();
+        if queue.should_notify() {
+            queue.notify();
+        }
+    }
 
-        let num_used_descs = outputs.len() + 1;
+    /// Adds the request to the virtqueue without notifying the device.
+    ///
+    /// If the virtqueue is full, this method notifies the device of the added
+    /// requests and waits for some of them to complete.
+    fn add_request(&self, bio_request: BioRequest) {
+        // Each request has an additional request header and a response status.
+        // A discard request also has a discard segment.
+        let num_descs =
+            bio_request.num_segments() + 2 + usize::from(bio_request.type_() == BioType::Discard);
         // FIXME: Split the request if it is too big
-        if num_used_descs > Self::QUEUE_SIZE as usize {
-            panic!("The request size surpasses the queue size");
-        }
+        assert!(
+            bio_request.num_segments() <= self.max_nr_segments_per_request,
+            "The request size surpasses the limit of the device"
+        );
 
         loop {
             let mut queue = self.queue.disable_irq().lock();
-            if num_used_descs > queue.available_desc() {
-                continue;
+            if queue.can_add(num_descs) {
+                self.add_request_to(&mut queue, bio_request);
+                return;
             }
-            let token = queue
-                .add_dma_buf(&[&req_slice], outputs.as_slice())
-                .expect("add queue failed");
+
+            // The requests that have been added must be processed by the device
+            // before the virtqueue has free descriptors.
             if queue.should_notify() {
                 queue.notify();
             }
-
-            // Records the submitted request
-            let submitted_request = SubmittedRequest::new(id as u16, bio_request);
-            self.submitted_requests
-                .disable_irq()
-                .lock()
-                .insert(token, submitted_request);
-            return;
+            drop(queue);
+
+            self.free_desc_wait_queue.wait_until(|| {
+                self.queue
+                    .disable_irq()
+                    .lock()
+                    .can_add(num_descs)
+                    .then_some(())
+            });
         }
     }
 
-    /// Writes data to the device, this function is non-blocking.
-    fn write(&self, bio_request: BioRequest) {
+    fn add_request_to(&self, queue: &mut VirtQueue, bio_request: BioRequest) {
         let id = self.id_allocator.disable_irq().lock().alloc().unwrap();
+        let (type_, sector) = match bio_request.type_() {
+            BioType::Read => (ReqType::In, bio_request.sid_range().start.to_raw()),
+            BioType::Write => (ReqType::Out, bio_request.sid_range().start.to_raw()),
+            BioType::Flush => (ReqType::Flush, 0),
+            BioType::Discard => (ReqType::Discard, 0),
+        };
+
         let req_slice = {
             let req_slice =
                 DmaStreamSlice::new(self.block_requests.clone(), id * REQ_SIZE, REQ_SIZE);
             let req = BlockReq {
-                type_: ReqType::Out as _,
+                type_: type_ as _,
                 reserved: 0,
-                sector: bio_request.sid_range().start.to_raw(),
+                sector,
             };
             req_slice.write_val(0, &req).unwrap();
             req_slice.sync().unwrap();
@@ -391,96 +483,72 @@ impl DeviceInner {
             resp_slice
         };
 
-        let inputs = {
-            let mut inputs: Vec<&DmaStreamSlice<_>> =
-                Vec::with_capacity(bio_request.num_segments() + 1);
-            inputs.push(&req_slice);
-            let dma_slices_iter = bio_request.bios().flat_map(|bio| {
-                bio.segments()
-                    .iter()
-                    .map(|segment| segment.inner_dma_slice())
-            });
-            inputs.extend(dma_slices_iter);
-            inputs
-        };
-
-        let num_used_descs = inputs.len() + 1;
-        // FIXME: Split the request if it is too big
-        if num_used_descs > Self::QUEUE_SIZE as usize {
-            panic!("The request size surpasses the queue size");
-        }
-        loop {
-            let mut queue = self.queue.disable_irq().lock();
-            if num_used_descs > queue.available_desc() {
-                continue;
-            }
-            let token = queue
-                .add_dma_buf(inputs.as_slice(), &[&resp_slice])
-                .expect("add queue failed");
-            if queue.should_notify() {
-                queue.notify();
+        // The discard segment describes the sectors to discard.
+        let discard_slice = {
+            let discard_slice =
+                DmaStreamSlice::new(self.block_discards.clone(), id * DISCARD_SIZE, DISCARD_SIZE);
+            if bio_request.type_() == BioType::Discard {
+                let discard = DiscardSegment {
+                    sector: bio_request.sid_range().start.to_raw(),
+                    num_sectors: bio_request.num_sectors() as u32,
+                    flags: 0,
+                };
+                discard_slice.write_val(0, &discard).unwrap();
+                discard_slice.sync().unwrap();
             }
+            discard_slice
+        };
 
-            // Records the submitted request
-            let submitted_request = SubmittedRequest::new(id as u16, bio_request);
-            self.submitted_requests
-                .disable_irq()
-                .lock()
-                .insert(token, submitted_request);
-            return;
-        }
-    }
-
-    /// Flushes any cached data from the guest to the persistent storage on the host.
-    /// This will be ignored if the device doesn't support the `VIRTIO_BLK_F_FLUSH` feature.
-    fn flush(&self, bio_request: BioRequest) {
-        if self.features.support_flush {
-            bio_request.bios().for_each(|bio| {
-                bio.complete(BioStatus::Complete);
-            });
-            return;
+        // The data buffers are readable by the device for writes, and writable
+        // by the device for reads.
+        let dma_slices = bio_request.bios().flat_map(|bio| {
+            bio.segments()
+                .iter()
+                .map(|segment| segment.inner_dma_slice())
+        });
+        let mut inputs: Vec<&DmaStreamSlice<_>> = vec![&req_slice];
+        let mut outputs: Vec<&DmaStreamSlice<_>> = Vec::new();
+        match bio_request.type_() {
+            BioType::Write => inputs.extend(dma_slices),
+            BioType::Read => outputs.extend(dma_slices),
+            BioType::Discard => inputs.push(&discard_slice),
+            BioType::Flush => {}
         }
+        outputs.push(&resp_slice);
 
-        let id = self.id_allocator.disable_irq().lock().alloc().unwrap();
-        let req_slice = {
-            let req_slice = DmaStreamSlice::new(&self.block_requests, id * REQ_SIZE, REQ_SIZE);
-            let req = BlockReq {
-                type_: ReqType::Flush as _,
-                reserved: 0,
-                sector: bio_request.sid_range().start.to_raw(),
-            };
-            req_slice.write_val(0, &req).unwrap();
-            req_slice.sync().unwrap();
-            req_slice
-        };
+        let token = queue
+            .add_dma_buf(inputs.as_slice(), outputs.as_slice())
+            .expect("add queue failed");
 
-        let resp_slice = {
-            let resp_slice = DmaStreamSlice::new(&self.block_responses, id * RESP_SIZE, RESP_SIZE);
-            resp_slice.write_val(0, &BlockResp::default()).unwrap();
-            resp_slice
-        };
+        // Records the submitted request. The lock of the virtqueue is held, so
+        // the request cannot be completed before it is recorded.
+        let submitted_request = SubmittedRequest::new(id as u16, bio_request);
+        self.submitted_requests
+            .disable_irq()
+            .lock()
+            .insert(token, submitted_request);
+    }
+}
 
-        let num_used_descs = 1;
-        loop {
-            let mut queue = self.queue.disable_irq().lock();
-            if num_used_descs > queue.available_desc() {
-                continue;
-            }
-            let token = queue
-                .add_dma_buf(&[&req_slice], &[&resp_slice])
-                .expect("add queue failed");
-            if queue.should_notify() {
-                queue.notify();
-            }
+fn complete_bio_request(bio_request: &BioRequest, status: BioStatus) {
+    bio_request.bios().for_each(|bio| {
+        bio.complete(status);
+    });
+}
 
-            // Records the submitted request
-            let submitted_request = SubmittedRequest::new(id as u16, bio_request);
-            self.submitted_requests
-                .disable_irq()
-                .lock()
-                .insert(token, submitted_request);
-            return;
-        }
+impl Debug for DeviceInner {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("DeviceInner")
+            .field("config_manager", &self.config_manager)
+            .field("features", &self.features)
+            .field("queue", &self.queue)
+            .field("transport", &self.transport)
+            .field("submitted_requests", &self.submitted_requests)
+            .field(
+                "max_nr_segments_per_request",
+                &self.max_nr_segments_per_request,
+            )
+            .finish_non_exhaustive()
     }
 }
 
@@ -524,3 +592,14 @@ impl Default for BlockResp {
         }
     }
 }
+
+/// The segment of a VirtIOBlock discard request.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod)]
+struct DiscardSegment {
+    pub sector: u64,
+    pub num_sectors: u32,
+    pub flags: u32,
+}
+
+const DISCARD_SIZE: usize = size_of::<DiscardSegment>();
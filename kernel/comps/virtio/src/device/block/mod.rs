@@ -10,7 +10,10 @@ use bitflags::bitflags;
 use int_to_c_enum::TryFromInt;
 use ostd::Pod;
 
-use crate::transport::{ConfigManager, VirtioTransport};
+use crate::{
+    transport::{ConfigManager, VirtioTransport},
+    Feature,
+};
 
 pub static DEVICE_NAME: &str = "Virtio-Block";
 
@@ -119,6 +122,10 @@ pub struct VirtioBlockTopology {
 #[repr(C)]
 pub struct VirtioBlockFeature {
     support_flush: bool,
+    support_seg_max: bool,
+    support_discard: bool,
+    support_indirect_desc: bool,
+    support_event_idx: bool,
 }
 
 impl VirtioBlockConfig {
@@ -185,6 +192,19 @@ impl ConfigManager<VirtioBlockConfig> {
             .unwrap() as usize
     }
 
+    pub(self) fn seg_max(&self) -> u32 {
+        self.read_once::<u32>(offset_of!(VirtioBlockConfig, seg_max))
+            .unwrap()
+    }
+
+    /// Returns the maximum number of sectors in a discard segment.
+    ///
+    /// The value is valid only if `BlockFeatures::DISCARD` is negotiated.
+    pub(self) fn max_discard_sectors(&self) -> u32 {
+        self.read_once::<u32>(offset_of!(VirtioBlockConfig, max_discard_sectors))
+            .unwrap()
+    }
+
     pub(self) fn capacity_sectors(&self) -> usize {
         let cap_low = self
             .read_once::<u32>(offset_of!(VirtioBlockConfig, capacity))
@@ -199,7 +219,17 @@ impl ConfigManager<VirtioBlockConfig> {
 
 impl VirtioBlockFeature {
     pub(self) fn new(transport: &dyn VirtioTransport) -> Self {
-        let support_flush = transport.read_device_features() & BlockFeatures::FLUSH.bits() == 1;
-        VirtioBlockFeature { support_flush }
+        // All the features offered by the device are accepted by the driver,
+        // except for those removed in `BlockDevice::negotiate_features`.
+        let features = transport.read_device_features();
+        let block_features = BlockFeatures::from_bits_truncate(features);
+        let features = Feature::from_bits_truncate(features);
+        VirtioBlockFeature {
+            support_flush: block_features.contains(BlockFeatures::FLUSH),
+            support_seg_max: block_features.contains(BlockFeatures::SEG_MAX),
+            support_discard: block_features.contains(BlockFeatures::DISCARD),
+            support_indirect_desc: features.contains(Feature::RING_INDIRECT_DESC),
+            support_event_idx: features.contains(Feature::RING_EVENT_IDX),
+        }
     }
 }
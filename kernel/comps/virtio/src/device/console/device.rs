@@ -13,11 +13,16 @@ use ostd::{
 
 use super::{config::VirtioConsoleConfig, DEVICE_NAME};
 use crate::{
-    device::{console::config::ConsoleFeatures, VirtioDeviceError},
+    device::{console::config::ConsoleFeatures, VirtioDeviceError, VirtioDeviceType},
+    driver::{VirtioDevice, VirtioDriver},
     queue::VirtQueue,
     transport::{ConfigManager, VirtioTransport},
 };
 
+const RECV0_QUEUE_INDEX: u16 = 0;
+const TRANSMIT0_QUEUE_INDEX: u16 = 1;
+const QUEUE_SIZE: u16 = 2;
+
 pub struct ConsoleDevice {
     config_manager: ConfigManager<VirtioConsoleConfig>,
     transport: SpinLock<Box<dyn VirtioTransport>>,
@@ -77,24 +82,26 @@ impl Debug for ConsoleDevice {
     }
 }
 
-impl ConsoleDevice {
-    pub fn negotiate_features(features: u64) -> u64 {
+impl VirtioDriver for ConsoleDevice {
+    const DEVICE_TYPE: VirtioDeviceType = VirtioDeviceType::Console;
+
+    fn negotiate_features(features: u64) -> u64 {
         let mut features = ConsoleFeatures::from_bits_truncate(features);
         // A virtio console device may have multiple ports, but we only use one port to communicate now.
         features.remove(ConsoleFeatures::VIRTIO_CONSOLE_F_MULTIPORT);
         features.bits()
     }
 
-    pub fn init(mut transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
+    fn init(mut transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
         let config_manager = VirtioConsoleConfig::new_manager(transport.as_ref());
         debug!("virtio_console_config = {:?}", config_manager.read_config());
 
-        const RECV0_QUEUE_INDEX: u16 = 0;
-        const TRANSMIT0_QUEUE_INDEX: u16 = 1;
-        let receive_queue =
-            SpinLock::new(VirtQueue::new(RECV0_QUEUE_INDEX, 2, transport.as_mut()).unwrap());
-        let transmit_queue =
-            SpinLock::new(VirtQueue::new(TRANSMIT0_QUEUE_INDEX, 2, transport.as_mut()).unwrap());
+        let receive_queue = SpinLock::new(
+            VirtQueue::new(RECV0_QUEUE_INDEX, QUEUE_SIZE, transport.as_mut()).unwrap(),
+        );
+        let transmit_queue = SpinLock::new(
+            VirtQueue::new(TRANSMIT0_QUEUE_INDEX, QUEUE_SIZE, transport.as_mut()).unwrap(),
+        );
 
         let send_buffer = {
             let segment = FrameAllocOptions::new().alloc_segment(1).unwrap();
@@ -130,14 +137,35 @@ impl ConsoleDevice {
         transport
             .register_cfg_callback(Box::new(config_space_change))
             .unwrap();
-        transport.finish_init();
         drop(transport);
 
+        crate::driver::register_device(device.clone());
+
         aster_console::register_device(DEVICE_NAME.to_string(), device);
 
         Ok(())
     }
+}
+
+impl VirtioDevice for ConsoleDevice {
+    fn with_transport(&self, f: &mut dyn FnMut(&mut dyn VirtioTransport)) {
+        f(self.transport.disable_irq().lock().as_mut());
+    }
+
+    fn reinit(&self, transport: &mut dyn VirtioTransport) -> Result<(), VirtioDeviceError> {
+        // The bytes being sent or received are lost.
+        let mut receive_queue = self.receive_queue.disable_irq().lock();
+        *receive_queue = VirtQueue::new(RECV0_QUEUE_INDEX, QUEUE_SIZE, transport)?;
+        *self.transmit_queue.disable_irq().lock() =
+            VirtQueue::new(TRANSMIT0_QUEUE_INDEX, QUEUE_SIZE, transport)?;
+
+        self.activate_receive_buffer(&mut receive_queue);
+
+        Ok(())
+    }
+}
 
+impl ConsoleDevice {
     fn handle_recv_irq(&self) {
         let mut receive_queue = self.receive_queue.disable_irq().lock();
 
@@ -3,4 +3,5 @@
 pub mod config;
 pub mod device;
 
-pub static DEVICE_NAME: &str = "Virtio-Console";
+/// The name of the console, which is used by `console=` as in Linux.
+pub static DEVICE_NAME: &str = "hvc0";
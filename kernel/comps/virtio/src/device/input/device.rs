@@ -24,7 +24,11 @@ use ostd::{
 
 use super::{InputConfigSelect, VirtioInputConfig, VirtioInputEvent, QUEUE_EVENT, QUEUE_STATUS};
 use crate::{
-    device::VirtioDeviceError, dma_buf::DmaBuf, queue::VirtQueue, transport::VirtioTransport,
+    device::{VirtioDeviceError, VirtioDeviceType},
+    dma_buf::DmaBuf,
+    driver::{VirtioDevice, VirtioDriver},
+    queue::VirtQueue,
+    transport::VirtioTransport,
 };
 
 bitflags! {
@@ -79,10 +83,18 @@ pub struct InputDevice {
     transport: SpinLock<Box<dyn VirtioTransport>>,
 }
 
-impl InputDevice {
+impl VirtioDriver for InputDevice {
+    const DEVICE_TYPE: VirtioDeviceType = VirtioDeviceType::Input;
+
+    /// Negotiate features for the device specified bits 0~23
+    fn negotiate_features(features: u64) -> u64 {
+        assert_eq!(features, 0);
+        0
+    }
+
     /// Create a new VirtIO-Input driver.
     /// msix_vector_left should at least have one element or n elements where n is the virtqueue amount
-    pub fn init(mut transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
+    fn init(mut transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
         let mut event_queue = VirtQueue::new(QUEUE_EVENT, QUEUE_SIZE, transport.as_mut())
             .expect("create event virtqueue failed");
         let status_queue = VirtQueue::new(QUEUE_STATUS, QUEUE_SIZE, transport.as_mut())
@@ -137,14 +149,23 @@ impl InputDevice {
             .register_queue_callback(QUEUE_EVENT, Box::new(handle_input), false)
             .unwrap();
 
-        transport.finish_init();
         drop(transport);
 
+        crate::driver::register_device(device.clone());
+
         aster_input::register_device(super::DEVICE_NAME.to_string(), device);
 
         Ok(())
     }
+}
+
+impl VirtioDevice for InputDevice {
+    fn with_transport(&self, f: &mut dyn FnMut(&mut dyn VirtioTransport)) {
+        f(self.transport.disable_irq().lock().as_mut());
+    }
+}
 
+impl InputDevice {
     /// Pop the pending event.
     fn pop_pending_events(&self, handle_event: &impl Fn(&EventBuf) -> bool) {
         let mut event_queue = self.event_queue.disable_irq().lock();
@@ -240,12 +261,6 @@ impl InputDevice {
 
         self.pop_pending_events(&handle_event);
     }
-
-    /// Negotiate features for the device specified bits 0~23
-    pub(crate) fn negotiate_features(features: u64) -> u64 {
-        assert_eq!(features, 0);
-        0
-    }
 }
 
 /// A event table consists of many event buffers,
@@ -0,0 +1,85 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::mem::offset_of;
+
+use aster_util::safe_ptr::SafePtr;
+use ostd::Pod;
+
+use crate::transport::{ConfigManager, VirtioTransport};
+
+bitflags::bitflags! {
+    pub struct MemFeatures: u64 {
+        /// The `node_id` field is valid and is an ACPI PXM.
+        const VIRTIO_MEM_F_ACPI_PXM = 1 << 0;
+        /// The driver is not allowed to access unplugged memory.
+        const VIRTIO_MEM_F_UNPLUGGED_INACCESSIBLE = 1 << 1;
+        /// The plugged memory is preserved across system suspension.
+        const VIRTIO_MEM_F_PERSISTENT_SUSPEND = 1 << 2;
+    }
+}
+
+#[derive(Debug, Pod, Clone, Copy)]
+#[repr(C)]
+pub struct VirtioMemConfig {
+    /// The size and the alignment in bytes of a memory block.
+    pub block_size: u64,
+    /// The NUMA node of the device memory, valid with `VIRTIO_MEM_F_ACPI_PXM`.
+    pub node_id: u16,
+    padding: [u8; 6],
+    /// The start guest physical address of the device-managed memory region.
+    pub addr: u64,
+    /// The size in bytes of the device-managed memory region.
+    pub region_size: u64,
+    /// The size in bytes of the memory region that can be plugged currently.
+    pub usable_region_size: u64,
+    /// The size in bytes of the plugged memory in the device-managed region.
+    pub plugged_size: u64,
+    /// The size in bytes of the memory that the device wants to be plugged.
+    pub requested_size: u64,
+}
+
+impl VirtioMemConfig {
+    pub(super) fn new_manager(transport: &dyn VirtioTransport) -> ConfigManager<Self> {
+        let safe_ptr = transport
+            .device_config_mem()
+            .map(|mem| SafePtr::new(mem, 0));
+        let bar_space = transport.device_config_bar();
+        ConfigManager::new(safe_ptr, bar_space)
+    }
+}
+
+impl ConfigManager<VirtioMemConfig> {
+    pub(super) fn read_config(&self) -> VirtioMemConfig {
+        let mut mem_config = VirtioMemConfig::new_uninit();
+        mem_config.block_size = self.read_u64(offset_of!(VirtioMemConfig, block_size));
+        mem_config.node_id = self
+            .read_once::<u16>(offset_of!(VirtioMemConfig, node_id))
+            .unwrap();
+        mem_config.addr = self.read_u64(offset_of!(VirtioMemConfig, addr));
+        mem_config.region_size = self.read_u64(offset_of!(VirtioMemConfig, region_size));
+        mem_config.usable_region_size =
+            self.read_u64(offset_of!(VirtioMemConfig, usable_region_size));
+        mem_config.plugged_size = self.read_u64(offset_of!(VirtioMemConfig, plugged_size));
+        mem_config.requested_size = self.read_u64(offset_of!(VirtioMemConfig, requested_size));
+
+        mem_config
+    }
+
+    pub(super) fn usable_region_size(&self) -> u64 {
+        self.read_u64(offset_of!(VirtioMemConfig, usable_region_size))
+    }
+
+    pub(super) fn plugged_size(&self) -> u64 {
+        self.read_u64(offset_of!(VirtioMemConfig, plugged_size))
+    }
+
+    pub(super) fn requested_size(&self) -> u64 {
+        self.read_u64(offset_of!(VirtioMemConfig, requested_size))
+    }
+
+    fn read_u64(&self, offset: usize) -> u64 {
+        let low = self.read_once::<u32>(offset).unwrap() as u64;
+        let high = self.read_once::<u32>(offset + 4).unwrap() as u64;
+        (high << 32) | low
+    }
+}
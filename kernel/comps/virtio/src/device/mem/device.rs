@@ -0,0 +1,309 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{boxed::Box, sync::Arc};
+use core::{fmt::Debug, hint::spin_loop, ops::Range};
+
+use aster_softirq::Taskless;
+use id_alloc::IdAlloc;
+use log::{debug, info, warn};
+use ostd::{
+    mm::{frame::hotplug, DmaDirection, DmaStream, DmaStreamSlice, FrameAllocOptions, Paddr, VmIo},
+    sync::SpinLock,
+    trap::TrapFrame,
+};
+
+use super::{
+    config::{MemFeatures, VirtioMemConfig},
+    MemReq, MemResp, ReqType, RespType, REQ_SIZE, RESP_SIZE,
+};
+use crate::{
+    device::{VirtioDeviceError, VirtioDeviceType},
+    driver::{VirtioDevice, VirtioDriver},
+    queue::VirtQueue,
+    transport::{ConfigManager, VirtioTransport},
+};
+
+pub struct MemoryDevice {
+    config_manager: ConfigManager<VirtioMemConfig>,
+    transport: SpinLock<Box<dyn VirtioTransport>>,
+    guest_queue: SpinLock<VirtQueue>,
+    request_buffer: DmaStream,
+    response_buffer: DmaStream,
+    /// The start address of the device-managed memory region.
+    region_start: Paddr,
+    /// The size of a memory block reported by the device.
+    device_block_size: usize,
+    /// The size of a memory block managed by this driver.
+    ///
+    /// It is a multiple of `device_block_size` and is no less than the page size.
+    block_size: usize,
+    /// The number of memory blocks in the region.
+    nr_blocks: usize,
+    /// The plugged memory blocks, indexed from the start of the region.
+    plugged_blocks: SpinLock<PluggedBlocks>,
+}
+
+struct PluggedBlocks {
+    /// The allocation state of all the memory blocks in the region.
+    ids: IdAlloc,
+    /// The number of plugged memory blocks.
+    nr_plugged: usize,
+}
+
+impl Debug for MemoryDevice {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MemoryDevice")
+            .field("config", &self.config_manager.read_config())
+            .field("transport", &self.transport)
+            .field("guest_queue", &self.guest_queue)
+            .field("block_size", &self.block_size)
+            .finish()
+    }
+}
+
+impl VirtioDriver for MemoryDevice {
+    const DEVICE_TYPE: VirtioDeviceType = VirtioDeviceType::Memory;
+
+    fn negotiate_features(features: u64) -> u64 {
+        let mut features = MemFeatures::from_bits_truncate(features);
+        // We never access unplugged memory, so it is fine for the device to
+        // protect unplugged memory from being accessed by the guest.
+        //
+        // The NUMA node of the memory is not used now.
+        features.remove(MemFeatures::VIRTIO_MEM_F_ACPI_PXM);
+        features.bits()
+    }
+
+    fn init(mut transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
+        let config_manager = VirtioMemConfig::new_manager(transport.as_ref());
+        let config = config_manager.read_config();
+        debug!("virtio_mem_config = {:?}", config);
+
+        let device_block_size = config.block_size as usize;
+        let block_size = device_block_size.max(ostd::mm::PAGE_SIZE);
+        if device_block_size == 0
+            || block_size % device_block_size != 0
+            || config.addr as usize % block_size != 0
+        {
+            warn!(
+                "[Virtio-Mem]: Unsupported memory block size: {:#x}",
+                device_block_size
+            );
+            return Err(VirtioDeviceError::ConfigUnsupported);
+        }
+        let nr_blocks = config.region_size as usize / block_size;
+
+        const GUEST_QUEUE_INDEX: u16 = 0;
+        let guest_queue = SpinLock::new(
+            VirtQueue::new(GUEST_QUEUE_INDEX, Self::QUEUE_SIZE, transport.as_mut()).unwrap(),
+        );
+
+        let request_buffer = {
+            let segment = FrameAllocOptions::new().alloc_segment(1).unwrap();
+            DmaStream::map(segment.into(), DmaDirection::ToDevice, false).unwrap()
+        };
+        let response_buffer = {
+            let segment = FrameAllocOptions::new().alloc_segment(1).unwrap();
+            DmaStream::map(segment.into(), DmaDirection::FromDevice, false).unwrap()
+        };
+
+        let device = Arc::new(Self {
+            config_manager,
+            transport: SpinLock::new(transport),
+            guest_queue,
+            request_buffer,
+            response_buffer,
+            region_start: config.addr as Paddr,
+            device_block_size,
+            block_size,
+            nr_blocks,
+            plugged_blocks: SpinLock::new(PluggedBlocks {
+                ids: IdAlloc::with_capacity(nr_blocks),
+                nr_plugged: 0,
+            }),
+        });
+
+        // The resizing may take a long time, so it is done in the softirq context.
+        let resize_taskless = {
+            let device = device.clone();
+            Taskless::new(move || device.resize())
+        };
+
+        let mut transport = device.transport.disable_irq().lock();
+        let handle_config_change = {
+            let resize_taskless = resize_taskless.clone();
+            move |_: &TrapFrame| resize_taskless.schedule()
+        };
+        transport
+            .register_cfg_callback(Box::new(handle_config_change))
+            .unwrap();
+        drop(transport);
+
+        crate::driver::register_device(device.clone());
+
+        // The memory plugged before (e.g., prior to a reboot) is unknown to us.
+        if device.config_manager.plugged_size() != 0 {
+            info!("[Virtio-Mem]: Unplugging all the memory plugged before");
+            if device.send_request(ReqType::UnplugAll, 0, 0) != RespType::Ack {
+                warn!("[Virtio-Mem]: Failed to unplug all the memory");
+            }
+        }
+
+        info!(
+            "[Virtio-Mem]: Managing region {:#x?} with block size {:#x}",
+            device.region_start..device.region_start + config.region_size as usize,
+            block_size
+        );
+        resize_taskless.schedule();
+
+        Ok(())
+    }
+}
+
+impl VirtioDevice for MemoryDevice {
+    fn with_transport(&self, f: &mut dyn FnMut(&mut dyn VirtioTransport)) {
+        f(self.transport.disable_irq().lock().as_mut());
+    }
+}
+
+impl MemoryDevice {
+    const QUEUE_SIZE: u16 = 1;
+
+    /// Plugs or unplugs memory blocks until the plugged size meets the requested size.
+    fn resize(&self) {
+        let requested_size = self.config_manager.requested_size() as usize;
+
+        loop {
+            let plugged_size =
+                self.plugged_blocks.disable_irq().lock().nr_plugged * self.block_size;
+            let res = if plugged_size < requested_size {
+                self.plug_one_block()
+            } else if plugged_size > requested_size {
+                self.unplug_one_block()
+            } else {
+                break;
+            };
+            if res.is_err() {
+                break;
+            }
+        }
+    }
+
+    fn plug_one_block(&self) -> Result<(), RespType> {
+        let nr_usable_blocks = self.config_manager.usable_region_size() as usize / self.block_size;
+
+        let index = {
+            let mut plugged_blocks = self.plugged_blocks.disable_irq().lock();
+            let Some(index) = plugged_blocks.ids.alloc() else {
+                return Err(RespType::Nack);
+            };
+            if index >= nr_usable_blocks {
+                plugged_blocks.ids.free(index);
+                return Err(RespType::Nack);
+            }
+            index
+        };
+        let range = self.block_range(index);
+
+        let resp = self.send_request(ReqType::Plug, range.start, self.nr_device_blocks());
+        if resp != RespType::Ack {
+            debug!("[Virtio-Mem]: Failed to plug {:#x?}: {:?}", range, resp);
+            self.plugged_blocks.disable_irq().lock().ids.free(index);
+            return Err(resp);
+        }
+
+        if let Err(err) = hotplug::add_memory(range.clone()) {
+            warn!(
+                "[Virtio-Mem]: Failed to add the plugged memory {:#x?}: {:?}",
+                range, err
+            );
+            // Give the memory back since we cannot use it.
+            let _ = self.send_request(ReqType::Unplug, range.start, self.nr_device_blocks());
+            self.plugged_blocks.disable_irq().lock().ids.free(index);
+            return Err(RespType::Error);
+        }
+
+        self.plugged_blocks.disable_irq().lock().nr_plugged += 1;
+        Ok(())
+    }
+
+    fn unplug_one_block(&self) -> Result<(), RespType> {
+        let index = {
+            let plugged_blocks = self.plugged_blocks.disable_irq().lock();
+            // Unplug from the highest address to reduce the fragmentation.
+            let Some(index) = (0..self.nr_blocks)
+                .rev()
+                .find(|index| plugged_blocks.ids.is_allocated(*index))
+            else {
+                return Err(RespType::Nack);
+            };
+            index
+        };
+        let range = self.block_range(index);
+
+        if let Err(err) = hotplug::remove_memory(range.clone()) {
+            debug!(
+                "[Virtio-Mem]: Failed to remove the memory {:#x?}: {:?}",
+                range, err
+            );
+            return Err(RespType::Busy);
+        }
+
+        let resp = self.send_request(ReqType::Unplug, range.start, self.nr_device_blocks());
+        if resp != RespType::Ack {
+            debug!("[Virtio-Mem]: Failed to unplug {:#x?}: {:?}", range, resp);
+            // The memory is still plugged, so it is safe to use it again.
+            hotplug::add_memory(range).unwrap();
+            return Err(resp);
+        }
+
+        let mut plugged_blocks = self.plugged_blocks.disable_irq().lock();
+        plugged_blocks.ids.free(index);
+        plugged_blocks.nr_plugged -= 1;
+        Ok(())
+    }
+
+    fn block_range(&self, index: usize) -> Range<Paddr> {
+        let start = self.region_start + index * self.block_size;
+        start..start + self.block_size
+    }
+
+    fn nr_device_blocks(&self) -> u16 {
+        (self.block_size / self.device_block_size) as u16
+    }
+
+    /// Sends a request to the device and waits for the response.
+    fn send_request(&self, type_: ReqType, addr: Paddr, nb_blocks: u16) -> RespType {
+        let mut guest_queue = self.guest_queue.disable_irq().lock();
+
+        let req_slice = {
+            let req_slice = DmaStreamSlice::new(&self.request_buffer, 0, REQ_SIZE);
+            let req = MemReq {
+                type_: type_ as u16,
+                padding: [0; 3],
+                addr: addr as u64,
+                nb_blocks,
+                padding_1: [0; 3],
+            };
+            req_slice.write_val(0, &req).unwrap();
+            req_slice.sync().unwrap();
+            req_slice
+        };
+        let resp_slice = DmaStreamSlice::new(&self.response_buffer, 0, RESP_SIZE);
+
+        guest_queue
+            .add_dma_buf(&[&req_slice], &[&resp_slice])
+            .unwrap();
+        if guest_queue.should_notify() {
+            guest_queue.notify();
+        }
+        while !guest_queue.can_pop() {
+            spin_loop();
+        }
+        guest_queue.pop_used().unwrap();
+
+        resp_slice.sync().unwrap();
+        let resp: MemResp = resp_slice.read_val(0).unwrap();
+        RespType::try_from(resp.type_).unwrap_or(RespType::Error)
+    }
+}
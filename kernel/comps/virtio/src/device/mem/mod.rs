@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The virtio-mem device.
+//!
+//! A virtio-mem device manages a region of physical memory which can be
+//! plugged into or unplugged from the guest in the unit of blocks at
+//! runtime. The device asks the driver to resize the plugged memory by
+//! updating `requested_size` in its configuration space.
+
+pub mod config;
+pub mod device;
+
+use int_to_c_enum::TryFromInt;
+use ostd::Pod;
+
+pub static DEVICE_NAME: &str = "Virtio-Mem";
+
+#[repr(u16)]
+#[derive(Debug, Copy, Clone, TryFromInt)]
+pub enum ReqType {
+    /// Requests to plug memory blocks.
+    Plug = 0,
+    /// Requests to unplug memory blocks.
+    Unplug = 1,
+    /// Requests to unplug all plugged memory blocks.
+    UnplugAll = 2,
+    /// Requests the plugged state of memory blocks.
+    State = 3,
+}
+
+#[repr(u16)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, TryFromInt)]
+pub enum RespType {
+    /// The request succeeded.
+    Ack = 0,
+    /// The request was rejected.
+    Nack = 1,
+    /// The request cannot be processed now, but may succeed later.
+    Busy = 2,
+    /// The request was invalid.
+    Error = 3,
+}
+
+#[repr(u16)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, TryFromInt)]
+pub enum BlockState {
+    /// All the requested memory blocks are plugged.
+    Plugged = 0,
+    /// All the requested memory blocks are unplugged.
+    Unplugged = 1,
+    /// The requested memory blocks are partially plugged.
+    Mixed = 2,
+}
+
+#[derive(Debug, Copy, Clone, Pod)]
+#[repr(C)]
+struct MemReq {
+    type_: u16,
+    padding: [u16; 3],
+    /// The start address of the memory blocks.
+    ///
+    /// It is ignored for `UnplugAll` requests.
+    addr: u64,
+    /// The number of memory blocks.
+    ///
+    /// It is ignored for `UnplugAll` requests.
+    nb_blocks: u16,
+    padding_1: [u16; 3],
+}
+
+#[derive(Debug, Copy, Clone, Pod)]
+#[repr(C)]
+struct MemResp {
+    type_: u16,
+    padding: [u16; 3],
+    /// The state of the memory blocks, only valid for `State` requests.
+    state: u16,
+}
+
+const REQ_SIZE: usize = size_of::<MemReq>();
+const RESP_SIZE: usize = size_of::<MemResp>();
@@ -7,8 +7,11 @@ use crate::queue::QueueError;
 pub mod block;
 pub mod console;
 pub mod input;
+pub mod mem;
 pub mod network;
+pub mod scsi;
 pub mod socket;
+pub mod sound;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, TryFromInt)]
 #[repr(u8)]
@@ -36,6 +39,7 @@ pub enum VirtioDeviceType {
     Pstore = 22,
     IOMMU = 23,
     Memory = 24,
+    Sound = 25,
 }
 
 #[derive(Debug)]
@@ -47,6 +51,12 @@ pub enum VirtioDeviceError {
     QueueUnknownError,
     /// The input virtio capability list contains invalid element
     CapabilityListError,
+    /// The device does not accept the features selected by the driver
+    FeaturesNotAccepted,
+    /// The driver does not support reinitializing the device after a reset
+    ReinitUnsupported,
+    /// The device configuration is not supported by the driver
+    ConfigUnsupported,
 }
 
 impl From<QueueError> for VirtioDeviceError {
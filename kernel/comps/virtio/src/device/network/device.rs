@@ -16,7 +16,8 @@ use ostd::{mm::DmaStream, sync::SpinLock, trap::TrapFrame};
 
 use super::{config::VirtioNetConfig, header::VirtioNetHdr};
 use crate::{
-    device::{network::config::NetworkFeatures, VirtioDeviceError},
+    device::{network::config::NetworkFeatures, VirtioDeviceError, VirtioDeviceType},
+    driver::{VirtioDevice, VirtioDriver},
     queue::{QueueError, VirtQueue},
     transport::{ConfigManager, VirtioTransport},
 };
@@ -52,8 +53,10 @@ impl PollStatistics {
     }
 }
 
-impl NetworkDevice {
-    pub(crate) fn negotiate_features(device_features: u64) -> u64 {
+impl VirtioDriver for NetworkDevice {
+    const DEVICE_TYPE: VirtioDeviceType = VirtioDeviceType::Network;
+
+    fn negotiate_features(device_features: u64) -> u64 {
         let device_features = NetworkFeatures::from_bits_truncate(device_features);
         let supported_features = NetworkFeatures::support_features();
         let network_features = device_features & supported_features;
@@ -69,7 +72,7 @@ impl NetworkDevice {
         network_features.bits()
     }
 
-    pub fn init(mut transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
+    fn init(mut transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
         let config_manager = VirtioNetConfig::new_manager(transport.as_ref());
         let config = config_manager.read_config();
         debug!("virtio_net_config = {:?}", config);
@@ -143,15 +146,21 @@ impl NetworkDevice {
             .register_queue_callback(QUEUE_RECV, Box::new(handle_recv_event), true)
             .unwrap();
 
-        device.transport.finish_init();
+        let device: Arc<SpinLock<_, BottomHalfDisabled>> = Arc::new(SpinLock::new(device));
+        crate::driver::register_device(device.clone());
 
-        aster_network::register_device(
-            super::DEVICE_NAME.to_string(),
-            Arc::new(SpinLock::new(device)),
-        );
+        aster_network::register_device(super::DEVICE_NAME.to_string(), device);
         Ok(())
     }
+}
+
+impl VirtioDevice for SpinLock<NetworkDevice, BottomHalfDisabled> {
+    fn with_transport(&self, f: &mut dyn FnMut(&mut dyn VirtioTransport)) {
+        f(self.lock().transport.as_mut());
+    }
+}
 
+impl NetworkDevice {
     /// Adds a `RxBuffer` to the receive queue.
     fn add_rx_buffer(&mut self, rx_buffer: RxBuffer) -> Result<(), VirtioNetError> {
         let token = self
@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::mem::offset_of;
+
+use aster_util::safe_ptr::SafePtr;
+use ostd::Pod;
+
+use crate::transport::{ConfigManager, VirtioTransport};
+
+#[derive(Debug, Pod, Clone, Copy)]
+#[repr(C)]
+pub struct VirtioScsiConfig {
+    /// The number of request queues.
+    pub num_queues: u32,
+    /// The maximum number of segments in a command.
+    pub seg_max: u32,
+    /// The maximum number of sectors transferred by a command.
+    pub max_sectors: u32,
+    /// The maximum number of linked commands sent to a logical unit.
+    pub cmd_per_lun: u32,
+    /// The size of the events in the event queue.
+    pub event_info_size: u32,
+    /// The size of the sense data in the responses.
+    pub sense_size: u32,
+    /// The size of the command descriptor blocks (CDBs) in the requests.
+    pub cdb_size: u32,
+    /// The maximum channel number, which is always zero.
+    pub max_channel: u16,
+    /// The maximum target number.
+    pub max_target: u16,
+    /// The maximum LUN number.
+    pub max_lun: u32,
+}
+
+impl VirtioScsiConfig {
+    pub(super) fn new_manager(transport: &dyn VirtioTransport) -> ConfigManager<Self> {
+        let safe_ptr = transport
+            .device_config_mem()
+            .map(|mem| SafePtr::new(mem, 0));
+        let bar_space = transport.device_config_bar();
+        ConfigManager::new(safe_ptr, bar_space)
+    }
+}
+
+impl ConfigManager<VirtioScsiConfig> {
+    pub(super) fn read_config(&self) -> VirtioScsiConfig {
+        let mut scsi_config = VirtioScsiConfig::new_uninit();
+        scsi_config.num_queues = self
+            .read_once::<u32>(offset_of!(VirtioScsiConfig, num_queues))
+            .unwrap();
+        scsi_config.seg_max = self
+            .read_once::<u32>(offset_of!(VirtioScsiConfig, seg_max))
+            .unwrap();
+        scsi_config.max_sectors = self
+            .read_once::<u32>(offset_of!(VirtioScsiConfig, max_sectors))
+            .unwrap();
+        scsi_config.cmd_per_lun = self
+            .read_once::<u32>(offset_of!(VirtioScsiConfig, cmd_per_lun))
+            .unwrap();
+        scsi_config.event_info_size = self
+            .read_once::<u32>(offset_of!(VirtioScsiConfig, event_info_size))
+            .unwrap();
+        scsi_config.sense_size = self
+            .read_once::<u32>(offset_of!(VirtioScsiConfig, sense_size))
+            .unwrap();
+        scsi_config.cdb_size = self
+            .read_once::<u32>(offset_of!(VirtioScsiConfig, cdb_size))
+            .unwrap();
+        scsi_config.max_channel = self
+            .read_once::<u16>(offset_of!(VirtioScsiConfig, max_channel))
+            .unwrap();
+        scsi_config.max_target = self
+            .read_once::<u16>(offset_of!(VirtioScsiConfig, max_target))
+            .unwrap();
+        scsi_config.max_lun = self
+            .read_once::<u32>(offset_of!(VirtioScsiConfig, max_lun))
+            .unwrap();
+
+        scsi_config
+    }
+}
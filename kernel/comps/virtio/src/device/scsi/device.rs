@@ -0,0 +1,266 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{boxed::Box, string::ToString, sync::Arc, vec, vec::Vec};
+use core::{fmt::Debug, hint::spin_loop};
+
+use log::{debug, info, warn};
+use ostd::{
+    mm::{DmaDirection, DmaStream, DmaStreamSlice, FrameAllocOptions, VmIo, PAGE_SIZE},
+    sync::SpinLock,
+};
+use spin::Once;
+
+use super::{
+    config::VirtioScsiConfig, CmdReqHdr, CmdRespHdr, ScsiData, ScsiError, ScsiLun, ScsiResponse,
+    ScsiResponseCode, DEVICE_NAME, MAX_TRANSFER_BYTES, TASK_ATTR_SIMPLE,
+};
+use crate::{
+    device::{VirtioDeviceError, VirtioDeviceType},
+    driver::{VirtioDevice, VirtioDriver},
+    queue::VirtQueue,
+    transport::{ConfigManager, VirtioTransport},
+};
+
+/// The index of the first request queue, which follows the control queue and the event queue.
+const REQUEST_QUEUE_INDEX: u16 = 2;
+const REQUEST_QUEUE_SIZE: u16 = 8;
+
+/// The maximum number of targets that are scanned for logical units.
+const MAX_SCANNED_TARGETS: u16 = 16;
+
+/// The opcode of the `INQUIRY` command.
+const INQUIRY: u8 = 0x12;
+/// The length of the standard `INQUIRY` data.
+const INQUIRY_LEN: u8 = 36;
+
+pub struct ScsiDevice {
+    config_manager: ConfigManager<VirtioScsiConfig>,
+    transport: SpinLock<Box<dyn VirtioTransport>>,
+    request_queue: SpinLock<VirtQueue>,
+    request_buffer: DmaStream,
+    response_buffer: DmaStream,
+    data_buffer: DmaStream,
+    cdb_size: usize,
+    sense_size: usize,
+    luns: Once<Vec<ScsiLun>>,
+}
+
+impl Debug for ScsiDevice {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ScsiDevice")
+            .field("config", &self.config_manager.read_config())
+            .field("transport", &self.transport)
+            .field("request_queue", &self.request_queue)
+            .field("luns", &self.luns.get())
+            .finish_non_exhaustive()
+    }
+}
+
+impl VirtioDriver for ScsiDevice {
+    const DEVICE_TYPE: VirtioDeviceType = VirtioDeviceType::ScsiHost;
+
+    fn negotiate_features(_features: u64) -> u64 {
+        // The bidirectional commands (i.e., `VIRTIO_SCSI_F_INOUT`) and the hotplug events (i.e.,
+        // `VIRTIO_SCSI_F_HOTPLUG`) are not supported.
+        0
+    }
+
+    fn init(mut transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
+        let config_manager = VirtioScsiConfig::new_manager(transport.as_ref());
+        let config = config_manager.read_config();
+        debug!("virtio_scsi_config = {:?}", config);
+
+        let cdb_size = config.cdb_size as usize;
+        let sense_size = config.sense_size as usize;
+        if size_of::<CmdReqHdr>() + cdb_size > PAGE_SIZE
+            || size_of::<CmdRespHdr>() + sense_size > PAGE_SIZE
+        {
+            warn!(
+                "[Virtio-SCSI]: The CDB size {} or the sense size {} is too large",
+                cdb_size, sense_size
+            );
+            return Err(VirtioDeviceError::QueueUnknownError);
+        }
+
+        // Only the first request queue is used, so the commands are not sent in parallel.
+        let request_queue = SpinLock::new(
+            VirtQueue::new(REQUEST_QUEUE_INDEX, REQUEST_QUEUE_SIZE, transport.as_mut()).unwrap(),
+        );
+
+        let request_buffer = {
+            let segment = FrameAllocOptions::new().alloc_segment(1).unwrap();
+            DmaStream::map(segment.into(), DmaDirection::ToDevice, false).unwrap()
+        };
+        let response_buffer = {
+            let segment = FrameAllocOptions::new().alloc_segment(1).unwrap();
+            DmaStream::map(segment.into(), DmaDirection::FromDevice, false).unwrap()
+        };
+        let data_buffer = {
+            let segment = FrameAllocOptions::new()
+                .alloc_segment(MAX_TRANSFER_BYTES / PAGE_SIZE)
+                .unwrap();
+            DmaStream::map(segment.into(), DmaDirection::Bidirectional, false).unwrap()
+        };
+
+        let device = Arc::new(Self {
+            config_manager,
+            transport: SpinLock::new(transport),
+            request_queue,
+            request_buffer,
+            response_buffer,
+            data_buffer,
+            cdb_size,
+            sense_size,
+            luns: Once::new(),
+        });
+
+        crate::driver::register_device(device.clone());
+
+        // The commands can only be sent after the device is ready.
+        let nr_targets = (config.max_target + 1).min(MAX_SCANNED_TARGETS);
+        let luns = device.scan_luns(nr_targets);
+        info!("[Virtio-SCSI]: Found logical units: {:?}", luns);
+        device.luns.call_once(|| luns);
+
+        super::register_device(DEVICE_NAME.to_string(), device);
+
+        Ok(())
+    }
+}
+
+impl VirtioDevice for ScsiDevice {
+    fn with_transport(&self, f: &mut dyn FnMut(&mut dyn VirtioTransport)) {
+        f(self.transport.disable_irq().lock().as_mut());
+    }
+}
+
+impl ScsiDevice {
+    /// Returns the logical units found on the device.
+    pub fn luns(&self) -> &[ScsiLun] {
+        self.luns.get().map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Returns the maximum length of the command descriptor blocks (CDBs).
+    pub fn max_cdb_len(&self) -> usize {
+        self.cdb_size
+    }
+
+    /// Executes a SCSI command on a logical unit and waits for its completion.
+    ///
+    /// The commands are executed one by one and waited by polling, which is intended for the
+    /// pass-through of the management commands rather than the bulk data transfers.
+    ///
+    /// For [`ScsiData::FromDevice`], the buffer is filled with the received data, whose length is
+    /// the buffer length minus the residual in the response.
+    pub fn execute(
+        &self,
+        lun: ScsiLun,
+        cdb: &[u8],
+        mut data: ScsiData,
+    ) -> Result<ScsiResponse, ScsiError> {
+        if cdb.is_empty() || cdb.len() > self.cdb_size {
+            return Err(ScsiError::InvalidCdb);
+        }
+        if data.len() > MAX_TRANSFER_BYTES {
+            return Err(ScsiError::TransferTooLarge);
+        }
+
+        let mut request_queue = self.request_queue.disable_irq().lock();
+
+        let req_slice = {
+            let req_slice = DmaStreamSlice::new(
+                &self.request_buffer,
+                0,
+                size_of::<CmdReqHdr>() + self.cdb_size,
+            );
+            let hdr = CmdReqHdr {
+                lun: lun.to_bytes(),
+                id: 0,
+                task_attr: TASK_ATTR_SIMPLE,
+                prio: 0,
+                crn: 0,
+            };
+            req_slice.write_val(0, &hdr).unwrap();
+            // The CDB is padded with zeros to the size expected by the device.
+            let mut padded_cdb = vec![0u8; self.cdb_size];
+            padded_cdb[..cdb.len()].copy_from_slice(cdb);
+            req_slice
+                .write_bytes(size_of::<CmdReqHdr>(), &padded_cdb)
+                .unwrap();
+            req_slice.sync().unwrap();
+            req_slice
+        };
+        let resp_slice = DmaStreamSlice::new(
+            &self.response_buffer,
+            0,
+            size_of::<CmdRespHdr>() + self.sense_size,
+        );
+        let data_slice = DmaStreamSlice::new(&self.data_buffer, 0, data.len());
+
+        match &data {
+            ScsiData::None => request_queue.add_dma_buf(&[&req_slice], &[&resp_slice]),
+            ScsiData::ToDevice(buf) => {
+                data_slice.write_bytes(0, buf).unwrap();
+                data_slice.sync().unwrap();
+                request_queue.add_dma_buf(&[&req_slice, &data_slice], &[&resp_slice])
+            }
+            ScsiData::FromDevice(_) => {
+                request_queue.add_dma_buf(&[&req_slice], &[&resp_slice, &data_slice])
+            }
+        }
+        .unwrap();
+        if request_queue.should_notify() {
+            request_queue.notify();
+        }
+        while !request_queue.can_pop() {
+            spin_loop();
+        }
+        request_queue.pop_used().unwrap();
+
+        resp_slice.sync().unwrap();
+        let hdr: CmdRespHdr = resp_slice.read_val(0).unwrap();
+        let mut sense = vec![0u8; (hdr.sense_len as usize).min(self.sense_size)];
+        resp_slice
+            .read_bytes(size_of::<CmdRespHdr>(), &mut sense)
+            .unwrap();
+
+        if let ScsiData::FromDevice(buf) = &mut data {
+            data_slice.sync().unwrap();
+            data_slice.read_bytes(0, buf).unwrap();
+        }
+
+        Ok(ScsiResponse {
+            code: ScsiResponseCode::try_from(hdr.response).unwrap_or(ScsiResponseCode::Failure),
+            status: hdr.status,
+            sense,
+            residual: hdr.resid,
+        })
+    }
+
+    /// Finds the logical units by sending `INQUIRY` to LUN 0 of each target.
+    ///
+    /// The other LUNs are not scanned now.
+    fn scan_luns(&self, nr_targets: u16) -> Vec<ScsiLun> {
+        let mut luns = Vec::new();
+
+        for target in 0..nr_targets {
+            let lun = ScsiLun {
+                target: target as u8,
+                lun: 0,
+            };
+            let cdb = [INQUIRY, 0, 0, 0, INQUIRY_LEN, 0];
+            let mut inquiry_data = [0u8; INQUIRY_LEN as usize];
+            let Ok(resp) = self.execute(lun, &cdb, ScsiData::FromDevice(&mut inquiry_data)) else {
+                continue;
+            };
+
+            // The peripheral qualifier (i.e., the high three bits) is zero if a logical unit is
+            // connected.
+            if resp.is_good() && inquiry_data[0] >> 5 == 0 {
+                luns.push(lun);
+            }
+        }
+
+        luns
+    }
+}
@@ -0,0 +1,172 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The virtio-SCSI device.
+//!
+//! A virtio-SCSI device is a SCSI host adapter, whose logical units are addressed by the target
+//! numbers and the LUNs. The driver sends the SCSI commands through the request queue, and the
+//! device reports the SCSI status and the sense data of each command in the response. Only the
+//! pass-through of the commands is supported now, i.e., the logical units are not used as block
+//! devices.
+
+pub mod config;
+pub mod device;
+
+use alloc::{collections::BTreeMap, string::String, sync::Arc, vec::Vec};
+
+use int_to_c_enum::TryFromInt;
+use ostd::{mm::PAGE_SIZE, sync::SpinLock, Pod};
+
+use self::device::ScsiDevice;
+
+pub static DEVICE_NAME: &str = "Virtio-SCSI";
+
+/// The maximum number of bytes transferred by a command.
+pub const MAX_TRANSFER_BYTES: usize = 16 * PAGE_SIZE;
+
+/// The SCSI status that reports the success of a command.
+pub const SCSI_STATUS_GOOD: u8 = 0x00;
+/// The SCSI status that reports the failure of a command, whose reason is in the sense data.
+pub const SCSI_STATUS_CHECK_CONDITION: u8 = 0x02;
+
+static SCSI_DEVICE_TABLE: SpinLock<BTreeMap<String, Arc<ScsiDevice>>> =
+    SpinLock::new(BTreeMap::new());
+
+pub(crate) fn register_device(name: String, device: Arc<ScsiDevice>) {
+    SCSI_DEVICE_TABLE.disable_irq().lock().insert(name, device);
+}
+
+pub fn get_device(name: &str) -> Option<Arc<ScsiDevice>> {
+    SCSI_DEVICE_TABLE.disable_irq().lock().get(name).cloned()
+}
+
+pub fn all_devices() -> Vec<(String, Arc<ScsiDevice>)> {
+    SCSI_DEVICE_TABLE
+        .disable_irq()
+        .lock()
+        .iter()
+        .map(|(name, device)| (name.clone(), device.clone()))
+        .collect()
+}
+
+/// The errors of the SCSI commands that are not sent to the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScsiError {
+    /// The command descriptor block (CDB) is empty or too long for the device.
+    InvalidCdb,
+    /// The data transfer exceeds [`MAX_TRANSFER_BYTES`].
+    TransferTooLarge,
+}
+
+/// The address of a logical unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ScsiLun {
+    pub target: u8,
+    pub lun: u16,
+}
+
+impl ScsiLun {
+    /// Encodes the address in the single-level LUN structure used by virtio-SCSI.
+    fn to_bytes(self) -> [u8; 8] {
+        [
+            1,
+            self.target,
+            0x40 | (self.lun >> 8) as u8,
+            self.lun as u8,
+            0,
+            0,
+            0,
+            0,
+        ]
+    }
+}
+
+/// The data transfer of a SCSI command.
+#[derive(Debug)]
+pub enum ScsiData<'a> {
+    /// The command transfers no data.
+    None,
+    /// The command sends the data to the device.
+    ToDevice(&'a [u8]),
+    /// The command receives the data from the device.
+    FromDevice(&'a mut [u8]),
+}
+
+impl ScsiData<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::ToDevice(buf) => buf.len(),
+            Self::FromDevice(buf) => buf.len(),
+        }
+    }
+}
+
+/// The response code of a SCSI command, which reports the failures of the transport.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub enum ScsiResponseCode {
+    /// The command is completed, and the SCSI status is valid.
+    Ok = 0,
+    /// The data transfer exceeds the buffer.
+    Overrun = 1,
+    /// The command is aborted by a task management function.
+    Aborted = 2,
+    /// The target does not exist.
+    BadTarget = 3,
+    /// The command is aborted by a reset.
+    Reset = 4,
+    /// The device is busy, so the command can be retried.
+    Busy = 5,
+    /// The command fails because of a transport problem.
+    TransportFailure = 6,
+    /// The command fails because of a target problem.
+    TargetFailure = 7,
+    /// The command fails because of a problem of the I_T nexus.
+    NexusFailure = 8,
+    /// The command fails for other reasons.
+    Failure = 9,
+}
+
+/// The result of a SCSI command completed by the device.
+#[derive(Debug, Clone)]
+pub struct ScsiResponse {
+    pub code: ScsiResponseCode,
+    /// The SCSI status, e.g., [`SCSI_STATUS_GOOD`] or [`SCSI_STATUS_CHECK_CONDITION`].
+    pub status: u8,
+    /// The sense data, which is usually reported with [`SCSI_STATUS_CHECK_CONDITION`].
+    pub sense: Vec<u8>,
+    /// The number of bytes that are not transferred.
+    pub residual: u32,
+}
+
+impl ScsiResponse {
+    /// Returns whether the command succeeds.
+    pub fn is_good(&self) -> bool {
+        self.code == ScsiResponseCode::Ok && self.status == SCSI_STATUS_GOOD
+    }
+}
+
+/// The task attribute that allows the command to be reordered with other simple commands.
+const TASK_ATTR_SIMPLE: u8 = 0;
+
+/// The header of a command request, which is followed by the CDB.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C, packed)]
+struct CmdReqHdr {
+    lun: [u8; 8],
+    id: u64,
+    task_attr: u8,
+    prio: u8,
+    crn: u8,
+}
+
+/// The header of a command response, which is followed by the sense data.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct CmdRespHdr {
+    sense_len: u32,
+    resid: u32,
+    status_qualifier: u16,
+    status: u8,
+    response: u8,
+}
@@ -20,8 +20,9 @@ use crate::{
             buffer::{RX_BUFFER_POOL, TX_BUFFER_POOL},
             handle_recv_irq, register_device,
         },
-        VirtioDeviceError,
+        VirtioDeviceError, VirtioDeviceType,
     },
+    driver::{VirtioDevice, VirtioDriver},
     queue::{QueueError, VirtQueue},
     transport::VirtioTransport,
 };
@@ -45,9 +46,20 @@ pub struct SocketDevice {
     transport: Box<dyn VirtioTransport>,
 }
 
-impl SocketDevice {
+impl VirtioDriver for SocketDevice {
+    const DEVICE_TYPE: VirtioDeviceType = VirtioDeviceType::Socket;
+
+    /// Negotiate features for the device specified bits 0~23
+    fn negotiate_features(features: u64) -> u64 {
+        let device_features = VsockFeatures::from_bits_truncate(features);
+        let supported_features = VsockFeatures::supported_features();
+        let vsock_features = device_features & supported_features;
+        debug!("features negotiated: {:?}", vsock_features);
+        vsock_features.bits()
+    }
+
     /// Create a new vsock device
-    pub fn init(mut transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
+    fn init(mut transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
         let virtio_vsock_config = VirtioVsockConfig::new(transport.as_mut());
         debug!("virtio_vsock_config = {:?}", virtio_vsock_config);
         let guest_cid = field_ptr!(&virtio_vsock_config, VirtioVsockConfig, guest_cid_low)
@@ -110,16 +122,22 @@ impl SocketDevice {
             .register_queue_callback(QUEUE_RECV, Box::new(handle_vsock_event), false)
             .unwrap();
 
-        device.transport.finish_init();
+        let device: Arc<SpinLock<SocketDevice>> = Arc::new(SpinLock::new(device));
+        crate::driver::register_device(device.clone());
 
-        register_device(
-            super::DEVICE_NAME.to_string(),
-            Arc::new(SpinLock::new(device)),
-        );
+        register_device(super::DEVICE_NAME.to_string(), device);
 
         Ok(())
     }
+}
+
+impl VirtioDevice for SpinLock<SocketDevice> {
+    fn with_transport(&self, f: &mut dyn FnMut(&mut dyn VirtioTransport)) {
+        f(self.disable_irq().lock().transport.as_mut());
+    }
+}
 
+impl SocketDevice {
     /// Return the CID which has been assigned to this guest.
     pub fn guest_cid(&self) -> u64 {
         self.guest_cid
@@ -311,15 +329,6 @@ impl SocketDevice {
         }
         Ok(())
     }
-
-    /// Negotiate features for the device specified bits 0~23
-    pub(crate) fn negotiate_features(features: u64) -> u64 {
-        let device_features = VsockFeatures::from_bits_truncate(features);
-        let supported_features = VsockFeatures::supported_features();
-        let vsock_features = device_features & supported_features;
-        debug!("features negotiated: {:?}", vsock_features);
-        vsock_features.bits()
-    }
 }
 
 impl Debug for SocketDevice {
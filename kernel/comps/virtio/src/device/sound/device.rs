@@ -0,0 +1,517 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{boxed::Box, collections::BTreeMap, string::ToString, sync::Arc, vec, vec::Vec};
+use core::{fmt::Debug, hint::spin_loop};
+
+use log::{debug, info, warn};
+use ostd::{
+    mm::{DmaDirection, DmaStream, DmaStreamSlice, FrameAllocOptions, VmIo, PAGE_SIZE},
+    sync::SpinLock,
+    trap::TrapFrame,
+    Pod,
+};
+use spin::Once;
+
+use super::{
+    config::VirtioSoundConfig, PcmDirection, PcmInfo, PcmInfoResp, PcmParams, PcmReq,
+    PcmSetParamsReq, PcmXferHdr, PcmXferStatus, QueryInfoReq, ReqCode, RespStatus, SoundError,
+    DEVICE_NAME, MAX_BUFFER_BYTES, MAX_PERIODS,
+};
+use crate::{
+    device::{VirtioDeviceError, VirtioDeviceType},
+    driver::{VirtioDevice, VirtioDriver},
+    queue::VirtQueue,
+    transport::{ConfigManager, VirtioTransport},
+};
+
+const CONTROL_QUEUE_INDEX: u16 = 0;
+const TX_QUEUE_INDEX: u16 = 2;
+
+const CONTROL_QUEUE_SIZE: u16 = 4;
+/// The size of the TX queue, which can hold all the periods since each period takes three
+/// descriptors.
+const TX_QUEUE_SIZE: u16 = 64;
+
+/// The size of the header of a response in the control queue.
+const RESP_HDR_SIZE: usize = size_of::<u32>();
+/// The space reserved for the header and the status of a period.
+const XFER_SIZE: usize = 16;
+
+/// The callback that is called after some periods have been played.
+pub type PcmCallback = dyn Fn() + Send + Sync;
+
+pub struct SoundDevice {
+    config_manager: ConfigManager<VirtioSoundConfig>,
+    transport: SpinLock<Box<dyn VirtioTransport>>,
+    control_queue: SpinLock<VirtQueue>,
+    request_buffer: DmaStream,
+    response_buffer: DmaStream,
+    tx: SpinLock<TxState>,
+    streams: Once<Vec<PcmInfo>>,
+    callbacks: SpinLock<Vec<&'static PcmCallback>>,
+}
+
+struct TxState {
+    queue: VirtQueue,
+    /// The buffer of the playback stream whose parameters are set.
+    buffer: Option<PlaybackBuffer>,
+}
+
+/// The buffer of a playback stream, which is divided into periods.
+struct PlaybackBuffer {
+    stream_id: u32,
+    period_bytes: usize,
+    /// The PCM frames of all the periods.
+    frames: DmaStream,
+    /// The headers and the statuses of all the periods.
+    xfers: DmaStream,
+    /// The periods that are not being played.
+    ///
+    /// The last one is being filled with the PCM frames written by the user.
+    free_periods: Vec<usize>,
+    /// The number of bytes filled in the period being filled.
+    filled_bytes: usize,
+    /// The periods being played, indexed by their tokens in the TX queue.
+    busy_periods: BTreeMap<u16, usize>,
+}
+
+impl Debug for SoundDevice {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SoundDevice")
+            .field("config", &self.config_manager.read_config())
+            .field("transport", &self.transport)
+            .field("control_queue", &self.control_queue)
+            .field("streams", &self.streams.get())
+            .finish_non_exhaustive()
+    }
+}
+
+impl VirtioDriver for SoundDevice {
+    const DEVICE_TYPE: VirtioDeviceType = VirtioDeviceType::Sound;
+
+    fn negotiate_features(_features: u64) -> u64 {
+        // The control elements (i.e., `VIRTIO_SND_F_CTLS`) are not supported.
+        0
+    }
+
+    fn init(mut transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
+        let config_manager = VirtioSoundConfig::new_manager(transport.as_ref());
+        let config = config_manager.read_config();
+        debug!("virtio_sound_config = {:?}", config);
+
+        let control_queue = SpinLock::new(
+            VirtQueue::new(CONTROL_QUEUE_INDEX, CONTROL_QUEUE_SIZE, transport.as_mut()).unwrap(),
+        );
+        let tx_queue = VirtQueue::new(TX_QUEUE_INDEX, TX_QUEUE_SIZE, transport.as_mut()).unwrap();
+
+        let request_buffer = {
+            let segment = FrameAllocOptions::new().alloc_segment(1).unwrap();
+            DmaStream::map(segment.into(), DmaDirection::ToDevice, false).unwrap()
+        };
+        let response_buffer = {
+            let segment = FrameAllocOptions::new().alloc_segment(1).unwrap();
+            DmaStream::map(segment.into(), DmaDirection::FromDevice, false).unwrap()
+        };
+
+        let device = Arc::new(Self {
+            config_manager,
+            transport: SpinLock::new(transport),
+            control_queue,
+            request_buffer,
+            response_buffer,
+            tx: SpinLock::new(TxState {
+                queue: tx_queue,
+                buffer: None,
+            }),
+            streams: Once::new(),
+            callbacks: SpinLock::new(Vec::new()),
+        });
+
+        let mut transport = device.transport.disable_irq().lock();
+        let handle_tx = {
+            let device = device.clone();
+            move |_: &TrapFrame| device.handle_tx_irq()
+        };
+        transport
+            .register_queue_callback(TX_QUEUE_INDEX, Box::new(handle_tx), false)
+            .unwrap();
+        drop(transport);
+
+        crate::driver::register_device(device.clone());
+
+        // The requests can only be sent after the device is ready.
+        let streams = device.query_pcm_info(config.streams).map_err(|err| {
+            warn!("[Virtio-Sound]: Failed to query the PCM streams: {:?}", err);
+            VirtioDeviceError::QueueUnknownError
+        })?;
+        info!("[Virtio-Sound]: Found PCM streams: {:?}", streams);
+        device.streams.call_once(|| streams);
+
+        super::register_device(DEVICE_NAME.to_string(), device);
+
+        Ok(())
+    }
+}
+
+impl VirtioDevice for SoundDevice {
+    fn with_transport(&self, f: &mut dyn FnMut(&mut dyn VirtioTransport)) {
+        f(self.transport.disable_irq().lock().as_mut());
+    }
+}
+
+impl SoundDevice {
+    /// Returns the capabilities of the PCM streams, indexed by the stream IDs.
+    pub fn streams(&self) -> &[PcmInfo] {
+        self.streams.get().map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Registers a callback that is called after some periods have been played.
+    ///
+    /// The callback is called in the interrupt context.
+    pub fn register_callback(&self, callback: &'static PcmCallback) {
+        self.callbacks.disable_irq().lock().push(callback);
+    }
+
+    /// Sets the parameters of a playback stream and allocates its buffer.
+    ///
+    /// Only one playback stream can have a buffer at a time. Setting the parameters replaces the
+    /// buffer, so it fails with [`SoundError::Busy`] if some periods are still being played.
+    pub fn set_params(&self, stream_id: u32, params: &PcmParams) -> Result<(), SoundError> {
+        let info = self.playback_stream(stream_id)?;
+
+        let frame_bytes = params.frame_bytes();
+        let period_bytes = params.period_bytes as usize;
+        let buffer_bytes = params.buffer_bytes as usize;
+        if !info.supports_format(params.format)
+            || !info.supports_rate(params.rate)
+            || !(info.channels_min..=info.channels_max).contains(&params.channels)
+            || frame_bytes == 0
+            || period_bytes == 0
+            || period_bytes % frame_bytes != 0
+            || buffer_bytes % period_bytes != 0
+            || !(1..=MAX_PERIODS).contains(&(buffer_bytes / period_bytes))
+            || buffer_bytes > MAX_BUFFER_BYTES
+        {
+            return Err(SoundError::InvalidParams);
+        }
+
+        if self
+            .tx
+            .disable_irq()
+            .lock()
+            .buffer
+            .as_ref()
+            .is_some_and(|buffer| !buffer.busy_periods.is_empty())
+        {
+            return Err(SoundError::Busy);
+        }
+
+        let req = PcmSetParamsReq {
+            code: ReqCode::PcmSetParams as u32,
+            stream_id,
+            buffer_bytes: params.buffer_bytes,
+            period_bytes: params.period_bytes,
+            features: 0,
+            channels: params.channels,
+            format: params.format as u8,
+            rate: params.rate as u8,
+            padding: 0,
+        };
+        self.send_request(&req, &mut [])?;
+
+        let buffer = PlaybackBuffer::new(stream_id, period_bytes, buffer_bytes);
+        self.tx.disable_irq().lock().buffer = Some(buffer);
+
+        Ok(())
+    }
+
+    /// Prepares a playback stream after its parameters are set.
+    pub fn prepare(&self, stream_id: u32) -> Result<(), SoundError> {
+        self.send_pcm_request(ReqCode::PcmPrepare, stream_id)
+    }
+
+    /// Starts playing the periods that have been and will be written.
+    pub fn start(&self, stream_id: u32) -> Result<(), SoundError> {
+        self.send_pcm_request(ReqCode::PcmStart, stream_id)
+    }
+
+    /// Stops playing the periods.
+    ///
+    /// The periods that have been written but not played are kept until the stream is released.
+    pub fn stop(&self, stream_id: u32) -> Result<(), SoundError> {
+        self.send_pcm_request(ReqCode::PcmStop, stream_id)
+    }
+
+    /// Releases a playback stream.
+    ///
+    /// The periods that have not been played are dropped. The parameters should be set again
+    /// before the stream can be prepared.
+    pub fn release(&self, stream_id: u32) -> Result<(), SoundError> {
+        self.send_pcm_request(ReqCode::PcmRelease, stream_id)?;
+
+        // The device completes all the periods of the stream before the release completes.
+        self.reclaim_periods();
+        if let Some(buffer) = self.tx.disable_irq().lock().buffer.as_mut() {
+            buffer.filled_bytes = 0;
+        }
+        self.call_callbacks();
+
+        Ok(())
+    }
+
+    /// Writes PCM frames to the buffer of the playback stream.
+    ///
+    /// A period is sent to the device once it is filled. This method returns the number of bytes
+    /// written, which is zero if all the periods are being played.
+    pub fn write(&self, frames: &[u8]) -> Result<usize, SoundError> {
+        let mut tx = self.tx.disable_irq().lock();
+        let TxState { queue, buffer } = &mut *tx;
+        let buffer = buffer.as_mut().ok_or(SoundError::InvalidState)?;
+
+        let mut written_bytes = 0;
+        while written_bytes < frames.len() {
+            let Some(&period) = buffer.free_periods.last() else {
+                break;
+            };
+
+            let len = (frames.len() - written_bytes).min(buffer.period_bytes - buffer.filled_bytes);
+            let offset = period * buffer.period_bytes + buffer.filled_bytes;
+            buffer
+                .frames
+                .write_bytes(offset, &frames[written_bytes..written_bytes + len])
+                .unwrap();
+            buffer.filled_bytes += len;
+            written_bytes += len;
+
+            if buffer.filled_bytes == buffer.period_bytes {
+                buffer.submit(queue);
+            }
+        }
+
+        Ok(written_bytes)
+    }
+
+    /// Sends the period being filled to the device, even if it is not full.
+    pub fn flush(&self) -> Result<(), SoundError> {
+        let mut tx = self.tx.disable_irq().lock();
+        let TxState { queue, buffer } = &mut *tx;
+        let buffer = buffer.as_mut().ok_or(SoundError::InvalidState)?;
+
+        if buffer.filled_bytes > 0 {
+            buffer.submit(queue);
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether some PCM frames can be written without blocking.
+    pub fn can_write(&self) -> bool {
+        self.tx
+            .disable_irq()
+            .lock()
+            .buffer
+            .as_ref()
+            .is_some_and(|buffer| !buffer.free_periods.is_empty())
+    }
+
+    /// Returns whether all the periods sent to the device have been played.
+    pub fn is_drained(&self) -> bool {
+        self.tx
+            .disable_irq()
+            .lock()
+            .buffer
+            .as_ref()
+            .is_none_or(|buffer| buffer.busy_periods.is_empty())
+    }
+
+    fn playback_stream(&self, stream_id: u32) -> Result<&PcmInfo, SoundError> {
+        self.streams()
+            .get(stream_id as usize)
+            .filter(|info| info.direction == PcmDirection::Output)
+            .ok_or(SoundError::InvalidStream)
+    }
+
+    fn handle_tx_irq(&self) {
+        self.reclaim_periods();
+        self.call_callbacks();
+    }
+
+    /// Reclaims the periods that have been played.
+    fn reclaim_periods(&self) {
+        let mut tx = self.tx.disable_irq().lock();
+        let TxState { queue, buffer } = &mut *tx;
+
+        while let Ok((token, _)) = queue.pop_used() {
+            let Some(buffer) = buffer.as_mut() else {
+                continue;
+            };
+            let Some(period) = buffer.busy_periods.remove(&token) else {
+                continue;
+            };
+
+            let status = buffer.status_slice(period);
+            status.sync().unwrap();
+            let status: PcmXferStatus = status.read_val(0).unwrap();
+            if status.status != RespStatus::Ok as u32 {
+                warn!(
+                    "[Virtio-Sound]: Failed to play a period of stream {}: {:#x}",
+                    buffer.stream_id, status.status
+                );
+            }
+
+            buffer.free_periods.push(period);
+        }
+    }
+
+    fn call_callbacks(&self) {
+        for callback in self.callbacks.disable_irq().lock().iter() {
+            callback();
+        }
+    }
+
+    fn query_pcm_info(&self, nr_streams: u32) -> Result<Vec<PcmInfo>, SoundError> {
+        const INFO_SIZE: usize = size_of::<PcmInfoResp>();
+        const MAX_STREAMS: usize = (PAGE_SIZE - RESP_HDR_SIZE) / INFO_SIZE;
+
+        let nr_streams = (nr_streams as usize).min(MAX_STREAMS);
+        let req = QueryInfoReq {
+            code: ReqCode::PcmInfo as u32,
+            start_id: 0,
+            count: nr_streams as u32,
+            size: INFO_SIZE as u32,
+        };
+        let mut resp = vec![0u8; nr_streams * INFO_SIZE];
+        self.send_request(&req, &mut resp)?;
+
+        let streams = resp
+            .chunks_exact(INFO_SIZE)
+            .map(|bytes| {
+                let info = PcmInfoResp::from_bytes(bytes);
+                PcmInfo {
+                    // The capture streams are not supported, so treat unknown directions as
+                    // capture to ignore them.
+                    direction: PcmDirection::try_from(info.direction)
+                        .unwrap_or(PcmDirection::Input),
+                    channels_min: info.channels_min,
+                    channels_max: info.channels_max,
+                    formats: info.formats,
+                    rates: info.rates,
+                }
+            })
+            .collect();
+        Ok(streams)
+    }
+
+    fn send_pcm_request(&self, code: ReqCode, stream_id: u32) -> Result<(), SoundError> {
+        self.playback_stream(stream_id)?;
+
+        let req = PcmReq {
+            code: code as u32,
+            stream_id,
+        };
+        self.send_request(&req, &mut [])
+    }
+
+    /// Sends a request to the control queue and waits for the response.
+    ///
+    /// The payload of the response, which follows the header, is copied to `resp`.
+    fn send_request<T: Pod>(&self, req: &T, resp: &mut [u8]) -> Result<(), SoundError> {
+        let mut control_queue = self.control_queue.disable_irq().lock();
+
+        let req_slice = {
+            let req_slice = DmaStreamSlice::new(&self.request_buffer, 0, size_of::<T>());
+            req_slice.write_val(0, req).unwrap();
+            req_slice.sync().unwrap();
+            req_slice
+        };
+        let resp_slice = DmaStreamSlice::new(&self.response_buffer, 0, RESP_HDR_SIZE + resp.len());
+
+        control_queue
+            .add_dma_buf(&[&req_slice], &[&resp_slice])
+            .unwrap();
+        if control_queue.should_notify() {
+            control_queue.notify();
+        }
+        while !control_queue.can_pop() {
+            spin_loop();
+        }
+        control_queue.pop_used().unwrap();
+
+        resp_slice.sync().unwrap();
+        let status: u32 = resp_slice.read_val(0).unwrap();
+        RespStatus::try_from(status)
+            .unwrap_or(RespStatus::IoErr)
+            .into_result()?;
+        resp_slice.read_bytes(RESP_HDR_SIZE, resp).unwrap();
+
+        Ok(())
+    }
+}
+
+impl PlaybackBuffer {
+    fn new(stream_id: u32, period_bytes: usize, buffer_bytes: usize) -> Self {
+        let nr_periods = buffer_bytes / period_bytes;
+
+        let frames = {
+            let nr_pages = buffer_bytes.div_ceil(PAGE_SIZE);
+            let segment = FrameAllocOptions::new().alloc_segment(nr_pages).unwrap();
+            DmaStream::map(segment.into(), DmaDirection::ToDevice, false).unwrap()
+        };
+        let xfers = {
+            let segment = FrameAllocOptions::new().alloc_segment(1).unwrap();
+            DmaStream::map(segment.into(), DmaDirection::Bidirectional, false).unwrap()
+        };
+
+        Self {
+            stream_id,
+            period_bytes,
+            frames,
+            xfers,
+            // Fill the periods in the ascending order.
+            free_periods: (0..nr_periods).rev().collect(),
+            filled_bytes: 0,
+            busy_periods: BTreeMap::new(),
+        }
+    }
+
+    /// Sends the period being filled to the device.
+    fn submit(&mut self, queue: &mut VirtQueue) {
+        let period = self.free_periods.pop().unwrap();
+
+        let hdr_slice = {
+            let hdr_slice =
+                DmaStreamSlice::new(&self.xfers, period * XFER_SIZE, size_of::<PcmXferHdr>());
+            let hdr = PcmXferHdr {
+                stream_id: self.stream_id,
+            };
+            hdr_slice.write_val(0, &hdr).unwrap();
+            hdr_slice.sync().unwrap();
+            hdr_slice
+        };
+        let frames_slice = {
+            let frames_slice =
+                DmaStreamSlice::new(&self.frames, period * self.period_bytes, self.filled_bytes);
+            frames_slice.sync().unwrap();
+            frames_slice
+        };
+        let status_slice = self.status_slice(period);
+
+        let token = queue
+            .add_dma_buf(&[&hdr_slice, &frames_slice], &[&status_slice])
+            .unwrap();
+        if queue.should_notify() {
+            queue.notify();
+        }
+
+        self.busy_periods.insert(token, period);
+        self.filled_bytes = 0;
+    }
+
+    fn status_slice(&self, period: usize) -> DmaStreamSlice<&DmaStream> {
+        DmaStreamSlice::new(
+            &self.xfers,
+            period * XFER_SIZE + size_of::<PcmXferHdr>(),
+            size_of::<PcmXferStatus>(),
+        )
+    }
+}
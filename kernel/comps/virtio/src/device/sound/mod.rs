@@ -0,0 +1,300 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The virtio-sound device.
+//!
+//! A virtio-sound device provides a number of PCM streams, jacks, and channel maps. Only the PCM
+//! playback streams are supported now. The driver configures and controls the streams with
+//! requests sent through the control queue. The PCM frames are sent to the device period by
+//! period through the TX queue, and the device returns each period after the period has been
+//! played, which paces the playback.
+
+pub mod config;
+pub mod device;
+
+use alloc::{collections::BTreeMap, string::String, sync::Arc, vec::Vec};
+
+use int_to_c_enum::TryFromInt;
+use ostd::{mm::PAGE_SIZE, sync::SpinLock, Pod};
+
+use self::device::SoundDevice;
+
+pub static DEVICE_NAME: &str = "Virtio-Sound";
+
+/// The maximum number of periods in the buffer of a playback stream.
+pub const MAX_PERIODS: usize = 16;
+/// The maximum size in bytes of the buffer of a playback stream.
+pub const MAX_BUFFER_BYTES: usize = 64 * PAGE_SIZE;
+
+static SOUND_DEVICE_TABLE: SpinLock<BTreeMap<String, Arc<SoundDevice>>> =
+    SpinLock::new(BTreeMap::new());
+
+pub(crate) fn register_device(name: String, device: Arc<SoundDevice>) {
+    SOUND_DEVICE_TABLE.disable_irq().lock().insert(name, device);
+}
+
+pub fn get_device(name: &str) -> Option<Arc<SoundDevice>> {
+    SOUND_DEVICE_TABLE.disable_irq().lock().get(name).cloned()
+}
+
+pub fn all_devices() -> Vec<(String, Arc<SoundDevice>)> {
+    SOUND_DEVICE_TABLE
+        .disable_irq()
+        .lock()
+        .iter()
+        .map(|(name, device)| (name.clone(), device.clone()))
+        .collect()
+}
+
+/// The errors of the PCM operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundError {
+    /// The stream does not exist or is not a playback stream.
+    InvalidStream,
+    /// The parameters are not supported by the stream.
+    InvalidParams,
+    /// The stream is not in a state that allows the operation.
+    InvalidState,
+    /// The PCM frames being played must be finished before the operation.
+    Busy,
+    /// The device reports that the request is malformed.
+    BadMessage,
+    /// The device reports that the request is not supported.
+    NotSupported,
+    /// The device reports an I/O error.
+    IoError,
+}
+
+/// The direction of a PCM stream.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub enum PcmDirection {
+    /// Playback.
+    Output = 0,
+    /// Capture.
+    Input = 1,
+}
+
+/// The sample format of a PCM stream.
+///
+/// Only the common linear formats in the little-endian byte order are listed.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub enum PcmFormat {
+    S8 = 3,
+    U8 = 4,
+    S16 = 5,
+    U16 = 6,
+    S32 = 17,
+    U32 = 18,
+    Float = 19,
+}
+
+impl PcmFormat {
+    /// Returns the number of bytes of a sample.
+    pub fn sample_bytes(self) -> usize {
+        match self {
+            Self::S8 | Self::U8 => 1,
+            Self::S16 | Self::U16 => 2,
+            Self::S32 | Self::U32 | Self::Float => 4,
+        }
+    }
+}
+
+/// The frame rate of a PCM stream.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub enum PcmRate {
+    Rate5512 = 0,
+    Rate8000 = 1,
+    Rate11025 = 2,
+    Rate16000 = 3,
+    Rate22050 = 4,
+    Rate32000 = 5,
+    Rate44100 = 6,
+    Rate48000 = 7,
+    Rate64000 = 8,
+    Rate88200 = 9,
+    Rate96000 = 10,
+    Rate176400 = 11,
+    Rate192000 = 12,
+    Rate384000 = 13,
+}
+
+impl PcmRate {
+    /// All the frame rates, in ascending order.
+    pub const ALL: [Self; 14] = [
+        Self::Rate5512,
+        Self::Rate8000,
+        Self::Rate11025,
+        Self::Rate16000,
+        Self::Rate22050,
+        Self::Rate32000,
+        Self::Rate44100,
+        Self::Rate48000,
+        Self::Rate64000,
+        Self::Rate88200,
+        Self::Rate96000,
+        Self::Rate176400,
+        Self::Rate192000,
+        Self::Rate384000,
+    ];
+
+    /// Returns the frame rate in Hz.
+    pub fn hz(self) -> u32 {
+        match self {
+            Self::Rate5512 => 5512,
+            Self::Rate8000 => 8000,
+            Self::Rate11025 => 11025,
+            Self::Rate16000 => 16000,
+            Self::Rate22050 => 22050,
+            Self::Rate32000 => 32000,
+            Self::Rate44100 => 44100,
+            Self::Rate48000 => 48000,
+            Self::Rate64000 => 64000,
+            Self::Rate88200 => 88200,
+            Self::Rate96000 => 96000,
+            Self::Rate176400 => 176400,
+            Self::Rate192000 => 192000,
+            Self::Rate384000 => 384000,
+        }
+    }
+}
+
+/// The capabilities of a PCM stream.
+#[derive(Debug, Clone, Copy)]
+pub struct PcmInfo {
+    /// The direction of the stream.
+    pub direction: PcmDirection,
+    /// The minimum number of channels.
+    pub channels_min: u8,
+    /// The maximum number of channels.
+    pub channels_max: u8,
+    formats: u64,
+    rates: u64,
+}
+
+impl PcmInfo {
+    /// Returns whether the stream supports the sample format.
+    pub fn supports_format(&self, format: PcmFormat) -> bool {
+        self.formats & (1 << format as u8) != 0
+    }
+
+    /// Returns whether the stream supports the frame rate.
+    pub fn supports_rate(&self, rate: PcmRate) -> bool {
+        self.rates & (1 << rate as u8) != 0
+    }
+}
+
+/// The parameters of a PCM stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PcmParams {
+    pub format: PcmFormat,
+    pub rate: PcmRate,
+    pub channels: u8,
+    /// The size in bytes of a period, i.e., the unit in which the frames are sent to the device.
+    pub period_bytes: u32,
+    /// The size in bytes of the buffer, which must be a multiple of `period_bytes`.
+    pub buffer_bytes: u32,
+}
+
+impl PcmParams {
+    /// Returns the number of bytes of a frame.
+    pub fn frame_bytes(&self) -> usize {
+        self.format.sample_bytes() * self.channels as usize
+    }
+}
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy)]
+enum ReqCode {
+    PcmInfo = 0x0100,
+    PcmSetParams = 0x0101,
+    PcmPrepare = 0x0102,
+    PcmRelease = 0x0103,
+    PcmStart = 0x0104,
+    PcmStop = 0x0105,
+}
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+enum RespStatus {
+    Ok = 0x8000,
+    BadMsg = 0x8001,
+    NotSupp = 0x8002,
+    IoErr = 0x8003,
+}
+
+impl RespStatus {
+    fn into_result(self) -> Result<(), SoundError> {
+        match self {
+            RespStatus::Ok => Ok(()),
+            RespStatus::BadMsg => Err(SoundError::BadMessage),
+            RespStatus::NotSupp => Err(SoundError::NotSupported),
+            RespStatus::IoErr => Err(SoundError::IoError),
+        }
+    }
+}
+
+/// The request to query the information of the jacks, the streams, or the channel maps.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct QueryInfoReq {
+    code: u32,
+    /// The ID of the first item to query.
+    start_id: u32,
+    /// The number of items to query.
+    count: u32,
+    /// The size in bytes of the information of each item.
+    size: u32,
+}
+
+/// The information of a PCM stream reported by the device.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct PcmInfoResp {
+    hda_fn_nid: u32,
+    features: u32,
+    formats: u64,
+    rates: u64,
+    direction: u8,
+    channels_min: u8,
+    channels_max: u8,
+    padding: [u8; 5],
+}
+
+/// The request to control a PCM stream.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct PcmReq {
+    code: u32,
+    stream_id: u32,
+}
+
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct PcmSetParamsReq {
+    code: u32,
+    stream_id: u32,
+    buffer_bytes: u32,
+    period_bytes: u32,
+    features: u32,
+    channels: u8,
+    format: u8,
+    rate: u8,
+    padding: u8,
+}
+
+/// The header of a period sent through the TX queue.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct PcmXferHdr {
+    stream_id: u32,
+}
+
+/// The status of a period returned through the TX queue.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct PcmXferStatus {
+    status: u32,
+    latency_bytes: u32,
+}
@@ -0,0 +1,307 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The device-independent part of the virtio drivers.
+//!
+//! This module drives the devices through the common steps of the virtio specification, so each
+//! device driver only needs to implement the device-specific logic:
+//!  - A driver implements [`VirtioDriver`] to select the device-specific features and to set up
+//!    the device after the features are negotiated.
+//!  - An initialized device implements [`VirtioDevice`] and is registered with
+//!    [`register_device`]. After that, the device is monitored for configuration change
+//!    interrupts. If the device asks for a reset, it is reset and reinitialized. If the device is
+//!    removed, it is no longer accessed.
+//!
+//! The transports found by the buses are added with [`add_transport`]. The transports found
+//! before the drivers are ready are initialized in [`probe_pending_devices`], and the others
+//! (e.g., hotplugged devices) are initialized as soon as they are found.
+
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aster_softirq::Taskless;
+use log::{error, info, warn};
+use ostd::{sync::SpinLock, trap::TrapFrame};
+
+use crate::{
+    device::{
+        block::device::BlockDevice, console::device::ConsoleDevice, input::device::InputDevice,
+        mem::device::MemoryDevice, network::device::NetworkDevice, scsi::device::ScsiDevice,
+        socket::device::SocketDevice, sound::device::SoundDevice, VirtioDeviceError,
+        VirtioDeviceType,
+    },
+    transport::{DeviceStatus, VirtioTransport},
+    Feature,
+};
+
+/// A driver of virtio devices of a specific type.
+pub(crate) trait VirtioDriver {
+    /// The type of the devices.
+    const DEVICE_TYPE: VirtioDeviceType;
+
+    /// Whether the driver supports the `VIRTIO_F_EVENT_IDX` feature.
+    const SUPPORTS_EVENT_IDX: bool = false;
+
+    /// Selects the supported features from the device-specific features (i.e., bits 0~23 and
+    /// 50~63) offered by the device.
+    fn negotiate_features(features: u64) -> u64;
+
+    /// Initializes the device after the features are negotiated.
+    ///
+    /// The driver should set up the virtqueues, register the interrupt callbacks, and then call
+    /// [`register_device`] to finish the initialization.
+    fn init(transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError>;
+}
+
+/// An initialized virtio device.
+pub(crate) trait VirtioDevice: Send + Sync {
+    /// Calls `f` with the transport of the device.
+    fn with_transport(&self, f: &mut dyn FnMut(&mut dyn VirtioTransport));
+
+    /// Reinitializes the device after it is reset and the features are negotiated again.
+    ///
+    /// The driver should set up the virtqueues again. The interrupt callbacks registered before
+    /// the reset remain valid.
+    ///
+    /// By default, the device cannot be reinitialized, so it will be marked as failed.
+    fn reinit(&self, _transport: &mut dyn VirtioTransport) -> Result<(), VirtioDeviceError> {
+        Err(VirtioDeviceError::ReinitUnsupported)
+    }
+}
+
+struct DriverEntry {
+    device_type: VirtioDeviceType,
+    supports_event_idx: bool,
+    negotiate_features: fn(u64) -> u64,
+    init: fn(Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError>,
+}
+
+impl DriverEntry {
+    const fn new<D: VirtioDriver>() -> Self {
+        Self {
+            device_type: D::DEVICE_TYPE,
+            supports_event_idx: D::SUPPORTS_EVENT_IDX,
+            negotiate_features: D::negotiate_features,
+            init: D::init,
+        }
+    }
+}
+
+static DRIVERS: [DriverEntry; 8] = [
+    DriverEntry::new::<BlockDevice>(),
+    DriverEntry::new::<InputDevice>(),
+    DriverEntry::new::<NetworkDevice>(),
+    DriverEntry::new::<ConsoleDevice>(),
+    DriverEntry::new::<SocketDevice>(),
+    DriverEntry::new::<MemoryDevice>(),
+    DriverEntry::new::<SoundDevice>(),
+    DriverEntry::new::<ScsiDevice>(),
+];
+
+fn find_driver(device_type: VirtioDeviceType) -> Option<&'static DriverEntry> {
+    DRIVERS
+        .iter()
+        .find(|driver| driver.device_type == device_type)
+}
+
+/// The transports that are found before the drivers are ready.
+///
+/// This is `None` after the drivers are ready.
+static PENDING_TRANSPORTS: SpinLock<Option<Vec<Box<dyn VirtioTransport>>>> =
+    SpinLock::new(Some(Vec::new()));
+
+/// Adds a transport found by a bus.
+///
+/// The device will be initialized immediately if the drivers are ready, or in
+/// [`probe_pending_devices`] otherwise.
+pub(crate) fn add_transport(transport: Box<dyn VirtioTransport>) {
+    let mut pending_transports = PENDING_TRANSPORTS.lock();
+    if let Some(pending_transports) = pending_transports.as_mut() {
+        pending_transports.push(transport);
+        return;
+    }
+    drop(pending_transports);
+
+    probe_device(transport);
+}
+
+/// Initializes the devices whose transports are found before the drivers are ready.
+///
+/// After this function is called, the devices found later are initialized immediately.
+pub(crate) fn probe_pending_devices() {
+    let pending_transports = PENDING_TRANSPORTS.lock().take().unwrap();
+    for transport in pending_transports {
+        probe_device(transport);
+    }
+}
+
+fn probe_device(mut transport: Box<dyn VirtioTransport>) {
+    let device_type = transport.device_type();
+    let Some(driver) = find_driver(device_type) else {
+        warn!("[Virtio]: Found unimplemented device:{:?}", device_type);
+        return;
+    };
+
+    if let Err(err) = start_init(transport.as_mut(), driver) {
+        error!(
+            "[Virtio]: Feature negotiation error:{:?}, device type:{:?}",
+            err, device_type
+        );
+        transport.write_device_status(DeviceStatus::FAILED).unwrap();
+        return;
+    }
+
+    if let Err(err) = (driver.init)(transport) {
+        error!(
+            "[Virtio]: Device initialization error:{:?}, device type:{:?}",
+            err, device_type
+        );
+    }
+}
+
+/// Resets the device and negotiates the features.
+///
+/// This performs the device-independent steps of the device initialization before the
+/// virtqueues are set up.
+fn start_init(
+    transport: &mut dyn VirtioTransport,
+    driver: &DriverEntry,
+) -> Result<(), VirtioDeviceError> {
+    transport.reset_device();
+    transport
+        .write_device_status(DeviceStatus::ACKNOWLEDGE | DeviceStatus::DRIVER)
+        .unwrap();
+
+    negotiate_features(transport, driver);
+
+    // Legacy devices do not have the `FEATURES_OK` status.
+    if transport.is_legacy_version() {
+        return Ok(());
+    }
+    let status = DeviceStatus::ACKNOWLEDGE | DeviceStatus::DRIVER | DeviceStatus::FEATURES_OK;
+    transport.write_device_status(status).unwrap();
+    // The device may reject the features by clearing the `FEATURES_OK` status.
+    if !transport
+        .read_device_status()
+        .contains(DeviceStatus::FEATURES_OK)
+    {
+        return Err(VirtioDeviceError::FeaturesNotAccepted);
+    }
+
+    Ok(())
+}
+
+fn negotiate_features(transport: &mut dyn VirtioTransport, driver: &DriverEntry) {
+    const DEVICE_SPECIFIC_MASK: u64 = ((1u64 << 24) - 1) | (((1u64 << 24) - 1) << 50);
+
+    let device_features = transport.read_device_features();
+    let device_specific_features = device_features & DEVICE_SPECIFIC_MASK;
+    let driver_specific_features = (driver.negotiate_features)(device_specific_features);
+
+    let mut generic_features = Feature::from_bits_truncate(device_features);
+    if !driver.supports_event_idx {
+        generic_features.remove(Feature::RING_EVENT_IDX);
+    }
+
+    let features = device_features & (generic_features.bits() | driver_specific_features);
+    transport.write_driver_features(features).unwrap();
+}
+
+/// A device that is managed by the framework.
+struct ManagedDevice {
+    device: Arc<dyn VirtioDevice>,
+    is_removed: AtomicBool,
+}
+
+/// The initialized devices.
+static DEVICES: SpinLock<Vec<Arc<ManagedDevice>>> = SpinLock::new(Vec::new());
+
+/// Registers an initialized device and finishes the initialization.
+///
+/// The device will be reset and reinitialized if it asks for a reset. It will be reset when the
+/// system is shutting down.
+pub(crate) fn register_device(device: Arc<dyn VirtioDevice>) {
+    let managed_device = Arc::new(ManagedDevice {
+        device,
+        is_removed: AtomicBool::new(false),
+    });
+
+    // The status check may take a long time if the device needs a reset, so it is done in the
+    // softirq context.
+    let check_status = {
+        let managed_device = managed_device.clone();
+        Taskless::new(move || managed_device.check_status())
+    };
+    let handle_config_change = move |_: &TrapFrame| check_status.schedule();
+
+    managed_device.device.with_transport(&mut |transport| {
+        transport
+            .register_cfg_callback(Box::new(handle_config_change.clone()))
+            .unwrap();
+        transport.finish_init();
+    });
+
+    DEVICES.disable_irq().lock().push(managed_device);
+}
+
+impl ManagedDevice {
+    /// Checks the device status after the configuration changes.
+    fn check_status(&self) {
+        if self.is_removed.load(Ordering::Relaxed) {
+            return;
+        }
+
+        self.device.with_transport(&mut |transport| {
+            if !transport.is_device_present() {
+                warn!("[Virtio]: Device removed:{:?}", transport.device_type());
+                self.is_removed.store(true, Ordering::Relaxed);
+                return;
+            }
+
+            if transport
+                .read_device_status()
+                .contains(DeviceStatus::DEVICE_NEEDS_RESET)
+            {
+                self.recover(transport);
+            }
+        });
+    }
+
+    /// Resets the device and reinitializes it.
+    fn recover(&self, transport: &mut dyn VirtioTransport) {
+        let device_type = transport.device_type();
+        info!("[Virtio]: Resetting device:{:?}", device_type);
+
+        let driver = find_driver(device_type).unwrap();
+        let res = start_init(transport, driver).and_then(|()| self.device.reinit(transport));
+        if let Err(err) = res {
+            error!(
+                "[Virtio]: Device recovery error:{:?}, device type:{:?}",
+                err, device_type
+            );
+            transport.write_device_status(DeviceStatus::FAILED).unwrap();
+            return;
+        }
+
+        transport.finish_init();
+    }
+
+    fn reset(&self) {
+        if self.is_removed.load(Ordering::Relaxed) {
+            return;
+        }
+
+        self.device
+            .with_transport(&mut |transport| transport.reset_device());
+    }
+}
+
+/// Resets all the initialized devices.
+///
+/// This should be called only when the system is shutting down. After the reset, the devices
+/// stop accessing the memory and raising interrupts, and they can no longer be used.
+pub fn reset_all_devices() {
+    let devices = DEVICES.disable_irq().lock();
+    for device in devices.iter() {
+        device.reset();
+    }
+}
@@ -13,7 +13,7 @@ use aster_util::{field_ptr, safe_ptr::SafePtr};
 use bitflags::bitflags;
 use log::debug;
 use ostd::{
-    mm::{DmaCoherent, FrameAllocOptions, PodOnce},
+    mm::{DmaCoherent, FrameAllocOptions, HasDaddr, PodOnce, PAGE_SIZE},
     Pod,
 };
 
@@ -62,6 +62,24 @@ pub struct VirtQueue {
     last_used_idx: u16,
     /// Whether the callback of this queue is enabled
     is_callback_enabled: bool,
+    /// Whether the event index (`VIRTIO_F_EVENT_IDX`) is used to suppress
+    /// the notifications and the interrupts.
+    is_event_idx_enabled: bool,
+    /// The index of the avail ring when the device was notified last time.
+    last_notified_avail_idx: u16,
+    /// The indirect descriptor tables, if `VIRTIO_F_INDIRECT_DESC` is used.
+    indirect_tables: Option<IndirectTables>,
+}
+
+/// The indirect descriptor tables, one for each descriptor in the ring.
+///
+/// A buffer whose head is the `i`-th descriptor in the ring uses the `i`-th
+/// table, so the tables of in-flight buffers never overlap.
+#[derive(Debug)]
+struct IndirectTables {
+    tables: Vec<SafePtr<Descriptor, DmaCoherent>>,
+    /// The maximum number of descriptors in a table.
+    max_descs: u16,
 }
 
 impl VirtQueue {
@@ -178,9 +196,67 @@ impl VirtQueue {
             avail_idx: 0,
             last_used_idx: 0,
             is_callback_enabled: true,
+            is_event_idx_enabled: false,
+            last_notified_avail_idx: 0,
+            indirect_tables: None,
         })
     }
 
+    /// Uses the event index to suppress the notifications and the interrupts.
+    ///
+    /// The driver must have negotiated the `VIRTIO_F_EVENT_IDX` feature.
+    pub fn enable_event_idx(&mut self) {
+        self.is_event_idx_enabled = true;
+        self.write_used_event(self.last_used_idx);
+    }
+
+    /// Uses the indirect descriptors for the buffers with multiple descriptors.
+    ///
+    /// Each of these buffers then occupies only one descriptor in the ring,
+    /// while it can have at most `max_descs` descriptors. The driver must
+    /// have negotiated the `VIRTIO_F_INDIRECT_DESC` feature.
+    pub fn enable_indirect_desc(&mut self, max_descs: u16) {
+        let table_size = size_of::<Descriptor>() * max_descs as usize;
+        let nframes = (table_size * self.queue_size as usize).div_ceil(PAGE_SIZE);
+        let dma = DmaCoherent::map(
+            FrameAllocOptions::new()
+                .alloc_segment(nframes)
+                .unwrap()
+                .into(),
+            true,
+        )
+        .unwrap();
+        let tables = (0..self.queue_size as usize)
+            .map(|i| SafePtr::new(dma.clone(), i * table_size))
+            .collect();
+
+        self.indirect_tables = Some(IndirectTables { tables, max_descs });
+    }
+
+    /// Returns the maximum number of descriptors in a buffer.
+    pub fn max_descs_per_buf(&self) -> usize {
+        match &self.indirect_tables {
+            Some(indirect_tables) => indirect_tables.max_descs as usize,
+            None => self.queue_size as usize,
+        }
+    }
+
+    /// Returns whether a buffer with `nr_descs` descriptors can be added now.
+    pub fn can_add(&self, nr_descs: usize) -> bool {
+        let nr_ring_descs = if self.uses_indirect_desc(nr_descs) {
+            1
+        } else {
+            nr_descs
+        };
+        nr_ring_descs <= self.available_desc()
+    }
+
+    fn uses_indirect_desc(&self, nr_descs: usize) -> bool {
+        self.indirect_tables
+            .as_ref()
+            .is_some_and(|tables| nr_descs > 1 && nr_descs <= tables.max_descs as usize)
+    }
+
     /// Add dma buffers to the virtqueue, return a token.
     ///
     /// Ref: linux virtio_ring.c virtqueue_add
@@ -192,6 +268,37 @@ impl VirtQueue {
         if inputs.is_empty() && outputs.is_empty() {
             return Err(QueueError::InvalidArgs);
         }
+
+        let head = if self.uses_indirect_desc(inputs.len() + outputs.len()) {
+            self.add_indirect_descs(inputs, outputs)?
+        } else {
+            self.add_descs(inputs, outputs)?
+        };
+
+        let avail_slot = self.avail_idx & (self.queue_size - 1);
+
+        {
+            let ring_ptr: SafePtr<[u16; 64], &DmaCoherent> =
+                field_ptr!(&self.avail, AvailRing, ring);
+            let mut ring_slot_ptr = ring_ptr.cast::<u16>();
+            ring_slot_ptr.add(avail_slot as usize);
+            ring_slot_ptr.write_once(&head).unwrap();
+        }
+        // write barrier
+        fence(Ordering::SeqCst);
+
+        // increase head of avail ring
+        self.avail_idx = self.avail_idx.wrapping_add(1);
+        field_ptr!(&self.avail, AvailRing, idx)
+            .write_once(&self.avail_idx)
+            .unwrap();
+
+        fence(Ordering::SeqCst);
+        Ok(head)
+    }
+
+    /// Adds the buffers to the ring descriptors and returns the head of them.
+    fn add_descs<T: DmaBuf>(&mut self, inputs: &[&T], outputs: &[&T]) -> Result<u16, QueueError> {
         if inputs.len() + outputs.len() + self.num_used as usize > self.queue_size as usize {
             return Err(QueueError::BufferTooSmall);
         }
@@ -231,25 +338,57 @@ impl VirtQueue {
         }
         self.num_used += (inputs.len() + outputs.len()) as u16;
 
-        let avail_slot = self.avail_idx & (self.queue_size - 1);
+        Ok(head)
+    }
 
-        {
-            let ring_ptr: SafePtr<[u16; 64], &DmaCoherent> =
-                field_ptr!(&self.avail, AvailRing, ring);
-            let mut ring_slot_ptr = ring_ptr.cast::<u16>();
-            ring_slot_ptr.add(avail_slot as usize);
-            ring_slot_ptr.write_once(&head).unwrap();
+    /// Adds the buffers to an indirect descriptor table, which is referred by
+    /// one descriptor in the ring, and returns the head of the ring descriptors.
+    fn add_indirect_descs<T: DmaBuf>(
+        &mut self,
+        inputs: &[&T],
+        outputs: &[&T],
+    ) -> Result<u16, QueueError> {
+        if self.num_used == self.queue_size {
+            return Err(QueueError::BufferTooSmall);
         }
-        // write barrier
-        fence(Ordering::SeqCst);
 
-        // increase head of avail ring
-        self.avail_idx = self.avail_idx.wrapping_add(1);
-        field_ptr!(&self.avail, AvailRing, idx)
-            .write_once(&self.avail_idx)
+        let head = self.free_head;
+        let table = &self.indirect_tables.as_ref().unwrap().tables[head as usize];
+        let nr_descs = inputs.len() + outputs.len();
+        let bufs = inputs
+            .iter()
+            .map(|buf| (*buf, DescFlags::empty()))
+            .chain(outputs.iter().map(|buf| (*buf, DescFlags::WRITE)));
+        for (i, (buf, flags)) in bufs.enumerate() {
+            let mut desc = table.clone();
+            desc.add(i);
+            set_dma_buf(&desc.borrow_vm().restrict::<TRights![Write, Dup]>(), buf);
+            let (flags, next) = if i + 1 < nr_descs {
+                (flags | DescFlags::NEXT, (i + 1) as u16)
+            } else {
+                (flags, 0)
+            };
+            field_ptr!(&desc, Descriptor, flags)
+                .write_once(&flags)
+                .unwrap();
+            field_ptr!(&desc, Descriptor, next)
+                .write_once(&next)
+                .unwrap();
+        }
+
+        let desc = &self.descs[head as usize];
+        field_ptr!(desc, Descriptor, addr)
+            .write_once(&(table.daddr() as u64))
+            .unwrap();
+        field_ptr!(desc, Descriptor, len)
+            .write_once(&((nr_descs * size_of::<Descriptor>()) as u32))
             .unwrap();
+        field_ptr!(desc, Descriptor, flags)
+            .write_once(&DescFlags::INDIRECT)
+            .unwrap();
+        self.free_head = field_ptr!(desc, Descriptor, next).read_once().unwrap();
+        self.num_used += 1;
 
-        fence(Ordering::SeqCst);
         Ok(head)
     }
 
@@ -317,6 +456,7 @@ impl VirtQueue {
 
         self.recycle_descriptors(index as u16);
         self.last_used_idx = self.last_used_idx.wrapping_add(1);
+        self.update_used_event();
 
         Ok((index as u16, len))
     }
@@ -345,6 +485,7 @@ impl VirtQueue {
 
         self.recycle_descriptors(index as u16);
         self.last_used_idx = self.last_used_idx.wrapping_add(1);
+        self.update_used_event();
 
         Ok(len)
     }
@@ -358,12 +499,24 @@ impl VirtQueue {
     pub fn should_notify(&self) -> bool {
         // read barrier
         fence(Ordering::SeqCst);
+
+        if self.is_event_idx_enabled {
+            // Notify the device if it asks to be notified at any of the
+            // buffers added since the last notification.
+            //
+            // Ref: linux virtio_ring.h vring_need_event
+            let avail_event = self.read_avail_event();
+            let nr_added = self.avail_idx.wrapping_sub(self.last_notified_avail_idx);
+            return self.avail_idx.wrapping_sub(avail_event).wrapping_sub(1) < nr_added;
+        }
+
         let flags = field_ptr!(&self.used, UsedRing, flags).read_once().unwrap();
         flags & 0x0001u16 == 0u16
     }
 
     /// notify that there are available rings
     pub fn notify(&mut self) {
+        self.last_notified_avail_idx = self.avail_idx;
         if self.notify_config.is_modern() {
             self.notify_config
                 .write_once::<u32>(0, self.queue_idx)
@@ -407,6 +560,31 @@ impl VirtQueue {
         flags_ptr.write_once(&flags).unwrap();
 
         self.is_callback_enabled = true;
+        self.update_used_event();
+    }
+
+    /// Asks the device to interrupt when the next used buffer is available.
+    ///
+    /// This takes effect only if the event index is enabled, in which case the
+    /// device ignores [`AvailFlags::VIRTQ_AVAIL_F_NO_INTERRUPT`].
+    fn update_used_event(&self) {
+        if self.is_event_idx_enabled && self.is_callback_enabled {
+            self.write_used_event(self.last_used_idx);
+        }
+    }
+
+    /// Writes the `used_event` field, which follows the ring in the available ring.
+    fn write_used_event(&self, used_event: u16) {
+        let mut ptr = self.avail.borrow_vm().cast::<u16>();
+        ptr.byte_add(offset_of!(AvailRing, ring) + self.queue_size as usize * size_of::<u16>());
+        ptr.write_once(&used_event).unwrap();
+    }
+
+    /// Reads the `avail_event` field, which follows the ring in the used ring.
+    fn read_avail_event(&self) -> u16 {
+        let mut ptr = self.used.borrow_vm().cast::<u16>();
+        ptr.byte_add(offset_of!(UsedRing, ring) + self.queue_size as usize * size_of::<UsedElem>());
+        ptr.read_once().unwrap()
     }
 }
 
@@ -456,7 +634,7 @@ pub struct AvailRing {
     /// A driver MUST NOT decrement the idx.
     idx: u16,
     ring: [u16; 64], // actual size: queue_size
-    used_event: u16, // unused
+    used_event: u16, // actual offset: after `queue_size` ring entries
 }
 
 /// The used ring is where the device returns buffers once it is done with them:
@@ -469,7 +647,7 @@ pub struct UsedRing {
     // the next index of the used element in ring array
     idx: u16,
     ring: [UsedElem; 64], // actual size: queue_size
-    avail_event: u16,     // unused
+    avail_event: u16,     // actual offset: after `queue_size` ring entries
 }
 
 #[repr(C)]
@@ -1,34 +1,23 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::{sync::Arc, vec::Vec};
+use alloc::{boxed::Box, sync::Arc};
 
-use ostd::{
-    bus::{
-        mmio::{
-            bus::{MmioDevice, MmioDriver},
-            common_device::MmioCommonDevice,
-        },
-        BusProbeError,
+use ostd::bus::{
+    mmio::{
+        bus::{MmioDevice, MmioDriver},
+        common_device::MmioCommonDevice,
     },
-    sync::SpinLock,
+    BusProbeError,
 };
 
 use super::device::VirtioMmioTransport;
 
 #[derive(Debug)]
-pub struct VirtioMmioDriver {
-    devices: SpinLock<Vec<VirtioMmioTransport>>,
-}
+pub struct VirtioMmioDriver;
 
 impl VirtioMmioDriver {
-    pub fn pop_device_transport(&self) -> Option<VirtioMmioTransport> {
-        self.devices.lock().pop()
-    }
-
     pub(super) fn new() -> Self {
-        VirtioMmioDriver {
-            devices: SpinLock::new(Vec::new()),
-        }
+        VirtioMmioDriver
     }
 }
 
@@ -39,7 +28,7 @@ impl MmioDriver for VirtioMmioDriver {
     ) -> Result<Arc<dyn MmioDevice>, (BusProbeError, MmioCommonDevice)> {
         let device = VirtioMmioTransport::new(device);
         let mmio_device = device.mmio_device().clone();
-        self.devices.lock().push(device);
+        crate::driver::add_transport(Box::new(device));
         Ok(mmio_device)
     }
 }
@@ -1,7 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use alloc::boxed::Box;
-use core::fmt::Debug;
+use core::{fmt::Debug, hint::spin_loop};
 
 use aster_util::safe_ptr::SafePtr;
 use ostd::{
@@ -57,6 +57,27 @@ pub trait VirtioTransport: Sync + Send + Debug {
         .unwrap();
     }
 
+    /// Resets the device.
+    ///
+    /// After the reset, the device stops using the virtqueues and raising interrupts.
+    fn reset_device(&mut self) {
+        self.write_device_status(DeviceStatus::empty()).unwrap();
+        while self.read_device_status() != DeviceStatus::empty() {
+            if !self.is_device_present() {
+                return;
+            }
+            spin_loop();
+        }
+    }
+
+    /// Checks whether the device is still present.
+    ///
+    /// A device may disappear at any time if the transport supports surprise removal. After
+    /// that, the accesses to the device have no effects.
+    fn is_device_present(&self) -> bool {
+        true
+    }
+
     /// Get access to the device config memory.
     fn device_config_mem(&self) -> Option<IoMem>;
 
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::boxed::Box;
+use alloc::{boxed::Box, collections::btree_map::BTreeMap};
 use core::fmt::Debug;
 
 use aster_util::{field_ptr, safe_ptr::SafePtr};
@@ -58,6 +58,10 @@ pub struct VirtioPciModernTransport {
     device_cfg: VirtioPciCapabilityData,
     notify: VirtioPciNotify,
     msix_manager: VirtioMsixManager,
+    /// The MSI-X vectors of the queues with interrupt callbacks.
+    queue_msix_vectors: BTreeMap<u16, u16>,
+    /// The MSI-X vector of configuration change interrupts, if a callback is registered.
+    config_msix_vector: Option<u16>,
 }
 
 impl Debug for VirtioPciModernTransport {
@@ -106,6 +110,18 @@ impl VirtioTransport for VirtioPciModernTransport {
         field_ptr!(&self.common_cfg, VirtioPciCommonCfg, queue_device)
             .write_once(&(used_ring_ptr.paddr() as u64))
             .unwrap();
+        // The MSI-X vectors are cleared when the device is reset. Restore them in case the queue
+        // is set up again after a reset.
+        if let Some(vector) = self.queue_msix_vectors.get(&idx) {
+            field_ptr!(&self.common_cfg, VirtioPciCommonCfg, queue_msix_vector)
+                .write_once(vector)
+                .unwrap();
+        }
+        if let Some(vector) = self.config_msix_vector {
+            field_ptr!(&self.common_cfg, VirtioPciCommonCfg, config_msix_vector)
+                .write_once(&vector)
+                .unwrap();
+        }
         // Enable queue
         field_ptr!(&self.common_cfg, VirtioPciCommonCfg, queue_enable)
             .write_once(&1u16)
@@ -187,7 +203,7 @@ impl VirtioTransport for VirtioPciModernTransport {
         let status = field_ptr!(&self.common_cfg, VirtioPciCommonCfg, device_status)
             .read_once()
             .unwrap();
-        DeviceStatus::from_bits(status).unwrap()
+        DeviceStatus::from_bits_truncate(status)
     }
 
     fn write_device_status(&mut self, status: DeviceStatus) -> Result<(), VirtioTransportError> {
@@ -248,6 +264,7 @@ impl VirtioTransport for VirtioPciModernTransport {
         field_ptr!(&self.common_cfg, VirtioPciCommonCfg, queue_msix_vector)
             .write_once(&vector)
             .unwrap();
+        self.queue_msix_vectors.insert(index, vector);
         Ok(())
     }
 
@@ -255,11 +272,19 @@ impl VirtioTransport for VirtioPciModernTransport {
         &mut self,
         func: Box<IrqCallbackFunction>,
     ) -> Result<(), VirtioTransportError> {
-        let (_, irq) = self.msix_manager.config_msix_irq();
+        let (vector, irq) = self.msix_manager.config_msix_irq();
         irq.on_active(func);
+        field_ptr!(&self.common_cfg, VirtioPciCommonCfg, config_msix_vector)
+            .write_once(&vector)
+            .unwrap();
+        self.config_msix_vector = Some(vector);
         Ok(())
     }
 
+    fn is_device_present(&self) -> bool {
+        self.common_device.is_present()
+    }
+
     fn is_legacy_version(&self) -> bool {
         // TODO: Support legacy version
         false
@@ -336,6 +361,8 @@ impl VirtioPciModernTransport {
             device_cfg,
             notify,
             msix_manager,
+            queue_msix_vectors: BTreeMap::new(),
+            config_msix_vector: None,
             device_type,
         })
     }
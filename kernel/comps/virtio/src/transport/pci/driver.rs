@@ -1,16 +1,13 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use alloc::{boxed::Box, sync::Arc};
 
-use ostd::{
-    bus::{
-        pci::{
-            bus::{PciDevice, PciDriver},
-            common_device::PciCommonDevice,
-        },
-        BusProbeError,
+use ostd::bus::{
+    pci::{
+        bus::{PciDevice, PciDriver},
+        common_device::PciCommonDevice,
     },
-    sync::SpinLock,
+    BusProbeError,
 };
 
 use super::device::VirtioPciModernTransport;
@@ -20,19 +17,11 @@ use crate::transport::{
 };
 
 #[derive(Debug)]
-pub struct VirtioPciDriver {
-    devices: SpinLock<Vec<Box<dyn VirtioTransport>>>,
-}
+pub struct VirtioPciDriver;
 
 impl VirtioPciDriver {
-    pub fn pop_device_transport(&self) -> Option<Box<dyn VirtioTransport>> {
-        self.devices.lock().pop()
-    }
-
     pub(super) fn new() -> Self {
-        VirtioPciDriver {
-            devices: SpinLock::new(Vec::new()),
-        }
+        VirtioPciDriver
     }
 }
 
@@ -59,7 +48,7 @@ impl PciDriver for VirtioPciDriver {
             }
             _ => return Err((BusProbeError::DeviceNotMatch, device)),
         };
-        self.devices.lock().push(transport);
+        crate::driver::add_transport(transport);
 
         Ok(Arc::new(VirtioPciDevice::new(device_id)))
     }
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::boxed::Box;
+use alloc::{boxed::Box, collections::btree_map::BTreeMap};
 use core::fmt::Debug;
 
 use aster_util::safe_ptr::SafePtr;
@@ -18,10 +18,10 @@ use ostd::{
 use crate::{
     queue::UsedElem,
     transport::{
-        pci::msix::VirtioMsixManager, AvailRing, ConfigManager, Descriptor, UsedRing,
+        pci::msix::VirtioMsixManager, AvailRing, ConfigManager, Descriptor, DeviceStatus, UsedRing,
         VirtioTransport, VirtioTransportError,
     },
-    DeviceStatus, VirtioDeviceType,
+    VirtioDeviceType,
 };
 
 // When used through the legacy interface, the virtio common configuration structure looks as follows:
@@ -68,6 +68,10 @@ pub struct VirtioPciLegacyTransport {
     config_bar: Bar,
     num_queues: u16,
     msix_manager: VirtioMsixManager,
+    /// The MSI-X vectors of the queues with interrupt callbacks.
+    queue_msix_vectors: BTreeMap<u16, u16>,
+    /// The MSI-X vector of configuration change interrupts, if a callback is registered.
+    config_msix_vector: Option<u16>,
 }
 
 impl VirtioPciLegacyTransport {
@@ -130,6 +134,8 @@ impl VirtioPciLegacyTransport {
             config_bar,
             num_queues,
             msix_manager,
+            queue_msix_vectors: BTreeMap::new(),
+            config_msix_vector: None,
         })
     }
 
@@ -187,6 +193,18 @@ impl VirtioTransport for VirtioPciLegacyTransport {
         self.config_bar
             .write_once(QUEUE_ADDR_PFN_OFFSET, page_frame_number)
             .unwrap();
+        // The MSI-X vectors are cleared when the device is reset. Restore them in case the queue
+        // is set up again after a reset.
+        if let Some(vector) = self.queue_msix_vectors.get(&idx) {
+            self.config_bar
+                .write_once(QUEUE_MSIX_VECTOR_OFFSET, *vector)
+                .unwrap();
+        }
+        if let Some(vector) = self.config_msix_vector {
+            self.config_bar
+                .write_once(CONFIG_MSIX_VECTOR_OFFSET, vector)
+                .unwrap();
+        }
         Ok(())
     }
 
@@ -306,6 +324,7 @@ impl VirtioTransport for VirtioPciLegacyTransport {
         self.config_bar
             .write_once(QUEUE_MSIX_VECTOR_OFFSET, vector)
             .unwrap();
+        self.queue_msix_vectors.insert(index, vector);
         Ok(())
     }
 
@@ -319,9 +338,14 @@ impl VirtioTransport for VirtioPciLegacyTransport {
         self.config_bar
             .write_once(CONFIG_MSIX_VECTOR_OFFSET, vector)
             .unwrap();
+        self.config_msix_vector = Some(vector);
         Ok(())
     }
 
+    fn is_device_present(&self) -> bool {
+        self.common_device.is_present()
+    }
+
     fn is_legacy_version(&self) -> bool {
         true
     }
@@ -87,6 +87,17 @@ pub mod tcp {
             }
         }
     }
+
+    /// An error returned by [`TcpConnection::recv_urgent`].
+    ///
+    /// [`TcpConnection::recv_urgent`]: crate::socket::TcpConnection::recv_urgent
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum RecvUrgentError {
+        /// There is no urgent data, or the urgent data is inline or has been read.
+        NoData,
+        /// The urgent pointer is received, but the urgent data has not arrived yet.
+        NotArrived,
+    }
 }
 
 pub mod udp {
@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::time::Duration;
+
 use crate::{iface::ScheduleNextPoll, socket::SocketEventObserver};
 
 /// Extension to be implemented by users of this crate.
@@ -19,4 +21,13 @@ pub trait Ext {
 
     /// The type for UDP sockets to observe events.
     type UdpEventObserver: SocketEventObserver;
+
+    /// Returns the current wall-clock time, which is used to timestamp the received packets.
+    fn now() -> Duration;
+
+    /// Returns the maximum number of TCP connections in the TIME-WAIT state.
+    ///
+    /// If the limit has been reached, a TCP connection will be closed instead of entering the
+    /// TIME-WAIT state.
+    fn max_time_wait_conns() -> usize;
 }
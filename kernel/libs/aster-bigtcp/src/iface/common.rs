@@ -6,20 +6,20 @@ use alloc::{
     sync::Arc,
     vec::Vec,
 };
-use core::sync::atomic::{AtomicU32, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 
 use aster_softirq::BottomHalfDisabled;
 use bitflags::bitflags;
 use int_to_c_enum::TryFromInt;
 use ostd::sync::{SpinLock, SpinLockGuard};
 use smoltcp::{
-    iface::{packet::Packet, Context},
+    iface::Context,
     phy::Device,
-    wire::{IpAddress, IpEndpoint, Ipv4Address, Ipv4Packet},
+    wire::{IpAddress, IpEndpoint, Ipv4Address, Ipv4Cidr, Ipv4Packet},
 };
 
 use super::{
-    poll::{FnHelper, PollContext, SocketTableAction},
+    poll::{FnHelper, OutgoingPacket, PollContext, SocketTableAction},
     poll_iface::PollableIface,
     port::BindPortConfig,
     time::get_network_timestamp,
@@ -39,7 +39,7 @@ pub struct IfaceCommon<E: Ext> {
     flags: InterfaceFlags,
 
     interface: SpinLock<PollableIface<E>, BottomHalfDisabled>,
-    used_ports: SpinLock<BTreeMap<u16, usize>, BottomHalfDisabled>,
+    used_ports: SpinLock<BTreeMap<u16, PortUsage>, BottomHalfDisabled>,
     sockets: SpinLock<SocketTable<E>, BottomHalfDisabled>,
     sched_poll: E::ScheduleNextPoll,
 }
@@ -90,6 +90,14 @@ impl<E: Ext> IfaceCommon<E> {
         self.interface.lock().prefix_len()
     }
 
+    pub(super) fn ipv4_cidr(&self) -> Option<Ipv4Cidr> {
+        self.interface.lock().ipv4_cidr()
+    }
+
+    pub(super) fn set_ipv4_cidr(&self, cidr: Option<Ipv4Cidr>) {
+        self.interface.lock().set_ipv4_cidr(cidr);
+    }
+
     pub(super) fn sched_poll(&self) -> &E::ScheduleNextPoll {
         &self.sched_poll
     }
@@ -113,8 +121,40 @@ impl<E: Ext> IfaceCommon<E> {
     }
 }
 
-const IP_LOCAL_PORT_START: u16 = 32768;
-const IP_LOCAL_PORT_END: u16 = 60999;
+/// The first port in the range of the ephemeral ports.
+pub const IP_LOCAL_PORT_START: u16 = 32768;
+/// The last port in the range of the ephemeral ports.
+pub const IP_LOCAL_PORT_END: u16 = 60999;
+
+/// The usage of a port.
+#[derive(Debug, Default)]
+struct PortUsage {
+    /// The number of sockets bound to the port.
+    num_sockets: usize,
+    /// The number of sockets bound to the port that do not allow it to be reused.
+    num_exclusive: usize,
+}
+
+impl PortUsage {
+    fn can_bind(&self, can_reuse: bool) -> bool {
+        // As in Linux, a port can be reused only if all sockets bound to it allow the reuse.
+        self.num_sockets == 0 || (can_reuse && self.num_exclusive == 0)
+    }
+
+    fn add(&mut self, can_reuse: bool) {
+        self.num_sockets += 1;
+        if !can_reuse {
+            self.num_exclusive += 1;
+        }
+    }
+
+    fn remove(&mut self, can_reuse: bool) {
+        self.num_sockets -= 1;
+        if !can_reuse {
+            self.num_exclusive -= 1;
+        }
+    }
+}
 
 impl<E: Ext> IfaceCommon<E> {
     pub(super) fn bind(
@@ -122,8 +162,12 @@ impl<E: Ext> IfaceCommon<E> {
         iface: Arc<dyn Iface<E>>,
         config: BindPortConfig,
     ) -> core::result::Result<BoundPort<E>, BindError> {
-        let port = self.bind_port(config)?;
-        Ok(BoundPort { iface, port })
+        let port = self.bind_port(&config)?;
+        Ok(BoundPort {
+            iface,
+            port,
+            can_reuse: AtomicBool::new(config.can_reuse()),
+        })
     }
 
     /// Allocates an unused ephemeral port.
@@ -131,50 +175,63 @@ impl<E: Ext> IfaceCommon<E> {
     /// We follow the port range that many Linux kernels use by default, which is 32768-60999.
     ///
     /// See <https://en.wikipedia.org/wiki/Ephemeral_port>.
-    fn alloc_ephemeral_port(&self) -> Option<u16> {
-        let mut used_ports = self.used_ports.lock();
-        for port in IP_LOCAL_PORT_START..=IP_LOCAL_PORT_END {
-            if let Entry::Vacant(e) = used_ports.entry(port) {
-                e.insert(0);
-                return Some(port);
-            }
-        }
-        None
+    fn alloc_ephemeral_port(used_ports: &BTreeMap<u16, PortUsage>) -> Option<u16> {
+        (IP_LOCAL_PORT_START..=IP_LOCAL_PORT_END).find(|port| !used_ports.contains_key(port))
     }
 
-    fn bind_port(&self, config: BindPortConfig) -> Result<u16, BindError> {
+    fn bind_port(&self, config: &BindPortConfig) -> Result<u16, BindError> {
+        let mut used_ports = self.used_ports.lock();
+
         let port = if let Some(port) = config.port() {
             port
         } else {
-            match self.alloc_ephemeral_port() {
-                Some(port) => port,
-                None => return Err(BindError::Exhausted),
-            }
+            Self::alloc_ephemeral_port(&used_ports).ok_or(BindError::Exhausted)?
         };
 
+        let usage = used_ports.entry(port).or_default();
+        if !usage.can_bind(config.can_reuse()) {
+            return Err(BindError::InUse);
+        }
+        usage.add(config.can_reuse());
+
+        Ok(port)
+    }
+
+    /// Binds another socket to the port, regardless of whether the port can be reused.
+    fn inherit_port(&self, bound: &BoundPort<E>) -> bool {
         let mut used_ports = self.used_ports.lock();
 
-        if let Some(used_times) = used_ports.get_mut(&port) {
-            if *used_times == 0 || config.can_reuse() {
-                // FIXME: Check if the previous socket was bound with SO_REUSEADDR.
-                *used_times += 1;
-            } else {
-                return Err(BindError::InUse);
-            }
-        } else {
-            used_ports.insert(port, 1);
+        let can_reuse = bound.can_reuse.load(Ordering::Relaxed);
+        if let Some(usage) = used_ports.get_mut(&bound.port) {
+            usage.add(can_reuse);
         }
 
-        Ok(port)
+        can_reuse
+    }
+
+    /// Updates whether the port can be reused by the socket.
+    fn set_port_reuse(&self, bound: &BoundPort<E>, can_reuse: bool) {
+        let mut used_ports = self.used_ports.lock();
+
+        let old_can_reuse = bound.can_reuse.swap(can_reuse, Ordering::Relaxed);
+        if let Some(usage) = used_ports.get_mut(&bound.port) {
+            usage.remove(old_can_reuse);
+            usage.add(can_reuse);
+        }
     }
 
     /// Releases the port so that it can be used again (if it is not being reused).
-    fn release_port(&self, port: u16) {
+    fn release_port(&self, bound: &BoundPort<E>) {
         let mut used_ports = self.used_ports.lock();
-        if let Some(used_times) = used_ports.remove(&port) {
-            if used_times != 1 {
-                used_ports.insert(port, used_times - 1);
-            }
+
+        let Entry::Occupied(mut entry) = used_ports.entry(bound.port) else {
+            return;
+        };
+        entry
+            .get_mut()
+            .remove(bound.can_reuse.load(Ordering::Relaxed));
+        if entry.get().num_sockets == 0 {
+            entry.remove();
         }
     }
 }
@@ -213,7 +270,7 @@ impl<E: Ext> IfaceCommon<E> {
             D::TxToken<'tx>,
             Option<(Ipv4Packet<&'pkt [u8]>, D::TxToken<'tx>)>,
         >,
-        Q: FnMut(&Packet, &mut Context, D::TxToken<'_>),
+        Q: FnMut(&OutgoingPacket, &mut Context, D::TxToken<'_>),
     {
         let mut interface = self.interface();
         interface.context_mut().now = get_network_timestamp();
@@ -252,6 +309,10 @@ impl<E: Ext> IfaceCommon<E> {
 pub struct BoundPort<E: Ext> {
     iface: Arc<dyn Iface<E>>,
     port: u16,
+    /// Whether the port can be reused by other sockets (i.e., `SO_REUSEADDR`).
+    ///
+    /// This is only modified while holding the lock of the used ports in the iface.
+    can_reuse: AtomicBool,
 }
 
 impl<E: Ext> BoundPort<E> {
@@ -273,11 +334,33 @@ impl<E: Ext> BoundPort<E> {
         };
         Some(IpEndpoint::new(ip_addr, self.port))
     }
+
+    /// Sets whether the port can be reused by other sockets.
+    ///
+    /// A port can be bound by a new socket only if the new socket and all the sockets that are
+    /// already bound to the port allow the port to be reused.
+    pub fn set_can_reuse(&self, can_reuse: bool) {
+        self.iface.common().set_port_reuse(self, can_reuse);
+    }
+
+    /// Binds another socket to the same port.
+    ///
+    /// The new socket inherits whether the port can be reused. Unlike binding the port via the
+    /// iface, this method always succeeds. It is used by connections accepted by a listener,
+    /// which share the port with the listener.
+    pub fn inherit(&self) -> Self {
+        let can_reuse = self.iface.common().inherit_port(self);
+        Self {
+            iface: self.iface.clone(),
+            port: self.port,
+            can_reuse: AtomicBool::new(can_reuse),
+        }
+    }
 }
 
 impl<E: Ext> Drop for BoundPort<E> {
     fn drop(&mut self) {
-        self.iface.common().release_port(self.port);
+        self.iface.common().release_port(self);
     }
 }
 
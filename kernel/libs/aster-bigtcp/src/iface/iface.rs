@@ -2,7 +2,7 @@
 
 use alloc::sync::Arc;
 
-use smoltcp::wire::Ipv4Address;
+use smoltcp::wire::{Ipv4Address, Ipv4Cidr};
 
 use super::{port::BindPortConfig, BoundPort, InterfaceFlags, InterfaceType};
 use crate::{errors::BindError, ext::Ext};
@@ -78,6 +78,18 @@ impl<E: Ext> dyn Iface<E> {
         self.common().prefix_len()
     }
 
+    /// Gets the IPv4 address of the iface with its prefix length, if any.
+    pub fn ipv4_cidr(&self) -> Option<Ipv4Cidr> {
+        self.common().ipv4_cidr()
+    }
+
+    /// Sets the IPv4 address of the iface with its prefix length.
+    ///
+    /// If `cidr` is `None`, the IPv4 address of the iface will be removed.
+    pub fn set_ipv4_cidr(&self, cidr: Option<Ipv4Cidr>) {
+        self.common().set_ipv4_cidr(cidr);
+    }
+
     /// Returns a reference to the associated [`ScheduleNextPoll`].
     pub fn sched_poll(&self) -> &E::ScheduleNextPoll {
         self.common().sched_poll()
@@ -10,7 +10,9 @@ mod port;
 mod sched;
 mod time;
 
-pub use common::{BoundPort, InterfaceFlags, InterfaceType};
+pub use common::{
+    BoundPort, InterfaceFlags, InterfaceType, IP_LOCAL_PORT_END, IP_LOCAL_PORT_START,
+};
 pub use iface::Iface;
 pub use phy::{EtherIface, IpIface};
 pub(crate) use poll_iface::{PollKey, PollableIfaceMut};
@@ -5,7 +5,7 @@ use alloc::{collections::btree_map::BTreeMap, string::String, sync::Arc};
 use aster_softirq::BottomHalfDisabled;
 use ostd::sync::SpinLock;
 use smoltcp::{
-    iface::{packet::Packet, Config, Context},
+    iface::{Config, Context},
     phy::{Device, DeviceCapabilities, TxToken},
     wire::{
         self, ArpOperation, ArpPacket, ArpRepr, EthernetAddress, EthernetFrame, EthernetProtocol,
@@ -19,6 +19,7 @@ use crate::{
     iface::{
         common::{IfaceCommon, InterfaceType},
         iface::internal::IfaceInternal,
+        poll::OutgoingPacket,
         time::get_network_timestamp,
         Iface, InterfaceFlags, ScheduleNextPoll,
     },
@@ -35,8 +36,8 @@ impl<D: WithDevice, E: Ext> EtherIface<D, E> {
     pub fn new(
         driver: D,
         ether_addr: EthernetAddress,
-        ip_cidr: Ipv4Cidr,
-        gateway: Ipv4Address,
+        ip_cidr: Option<Ipv4Cidr>,
+        gateway: Option<Ipv4Address>,
         name: String,
         sched_poll: E::ScheduleNextPoll,
         flags: InterfaceFlags,
@@ -46,14 +47,18 @@ impl<D: WithDevice, E: Ext> EtherIface<D, E> {
             let now = get_network_timestamp();
 
             let mut interface = smoltcp::iface::Interface::new(config, device, now);
-            interface.update_ip_addrs(|ip_addrs| {
-                debug_assert!(ip_addrs.is_empty());
-                ip_addrs.push(wire::IpCidr::Ipv4(ip_cidr)).unwrap();
-            });
-            interface
-                .routes_mut()
-                .add_default_ipv4_route(gateway)
-                .unwrap();
+            if let Some(ip_cidr) = ip_cidr {
+                interface.update_ip_addrs(|ip_addrs| {
+                    debug_assert!(ip_addrs.is_empty());
+                    ip_addrs.push(wire::IpCidr::Ipv4(ip_cidr)).unwrap();
+                });
+            }
+            if let Some(gateway) = gateway {
+                interface
+                    .routes_mut()
+                    .add_default_ipv4_route(gateway)
+                    .unwrap();
+            }
             interface
         });
 
@@ -197,7 +202,7 @@ impl<D, E: Ext> EtherIface<D, E> {
         }
     }
 
-    fn dispatch<T: TxToken>(&self, pkt: &Packet, iface_cx: &mut Context, tx_token: T) {
+    fn dispatch<T: TxToken>(&self, pkt: &OutgoingPacket, iface_cx: &mut Context, tx_token: T) {
         match self.resolve_ether_or_generate_arp(pkt, iface_cx) {
             Ok(ether) => Self::emit_ip(&ether, pkt, &iface_cx.caps, tx_token),
             Err(Some(arp)) => Self::emit_arp(&arp, tx_token),
@@ -207,7 +212,7 @@ impl<D, E: Ext> EtherIface<D, E> {
 
     fn resolve_ether_or_generate_arp(
         &self,
-        pkt: &Packet,
+        pkt: &OutgoingPacket,
         iface_cx: &mut Context,
     ) -> Result<EthernetRepr, Option<ArpRepr>> {
         // Resolve the next-hop IP address.
@@ -244,7 +249,7 @@ impl<D, E: Ext> EtherIface<D, E> {
     /// Consumes the token and emits an IP packet.
     fn emit_ip<T: TxToken>(
         ether_repr: &EthernetRepr,
-        ip_pkt: &Packet,
+        ip_pkt: &OutgoingPacket,
         caps: &DeviceCapabilities,
         tx_token: T,
     ) {
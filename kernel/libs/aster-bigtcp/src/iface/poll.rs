@@ -7,11 +7,11 @@ use smoltcp::{
         packet::{icmp_reply_payload_len, IpPayload, Packet},
         Context,
     },
-    phy::{ChecksumCapabilities, Device, RxToken, TxToken},
+    phy::{ChecksumCapabilities, Device, DeviceCapabilities, RxToken, TxToken},
     wire::{
-        Icmpv4DstUnreachable, Icmpv4Repr, IpAddress, IpProtocol, IpRepr, Ipv4Address, Ipv4Packet,
-        Ipv4Repr, TcpControl, TcpPacket, TcpRepr, UdpPacket, UdpRepr, IPV4_HEADER_LEN,
-        IPV4_MIN_MTU,
+        Icmpv4DstUnreachable, Icmpv4Message, Icmpv4Packet, Icmpv4Repr, IpAddress, IpEndpoint,
+        IpProtocol, IpRepr, Ipv4Address, Ipv4Packet, Ipv4Repr, TcpControl, TcpPacket, TcpRepr,
+        UdpPacket, UdpRepr, IPV4_HEADER_LEN, IPV4_MIN_MTU, UDP_HEADER_LEN,
     },
 };
 
@@ -20,8 +20,58 @@ use crate::{
     ext::Ext,
     socket::{TcpConnectionBg, TcpProcessResult},
     socket_table::{ConnectionKey, ListenerKey, SocketTable},
+    wire::{Ipv4Header, UdpHeader},
 };
 
+/// An outgoing IP packet.
+///
+/// This is a [`Packet`] that can carry a TCP urgent pointer, which cannot be represented by
+/// [`TcpRepr`].
+pub(super) struct OutgoingPacket<'p> {
+    packet: Packet<'p>,
+    tcp_urgent_ptr: Option<u16>,
+}
+
+impl<'p> OutgoingPacket<'p> {
+    fn new(packet: Packet<'p>) -> Self {
+        Self {
+            packet,
+            tcp_urgent_ptr: None,
+        }
+    }
+
+    fn new_tcp(ip_repr: IpRepr, tcp_repr: TcpRepr<'p>, urgent_ptr: Option<u16>) -> Self {
+        Self {
+            packet: Packet::new(ip_repr, IpPayload::Tcp(tcp_repr)),
+            tcp_urgent_ptr: urgent_ptr,
+        }
+    }
+
+    pub(super) fn ip_repr(&self) -> IpRepr {
+        self.packet.ip_repr()
+    }
+
+    pub(super) fn emit_payload(
+        &self,
+        ip_repr: &IpRepr,
+        payload: &mut [u8],
+        caps: &DeviceCapabilities,
+    ) {
+        self.packet.emit_payload(ip_repr, payload, caps);
+
+        let Some(urgent_ptr) = self.tcp_urgent_ptr else {
+            return;
+        };
+
+        let mut tcp_pkt = TcpPacket::new_unchecked(payload);
+        tcp_pkt.set_urg(true);
+        tcp_pkt.set_urgent_at(urgent_ptr);
+        if caps.checksum.tcp.tx() {
+            tcp_pkt.fill_checksum(&ip_repr.src_addr(), &ip_repr.dst_addr());
+        }
+    }
+}
+
 pub(super) struct PollContext<'a, E: Ext> {
     iface: PollableIfaceMut<'a, E>,
     sockets: &'a SocketTable<E>,
@@ -70,7 +120,7 @@ impl<E: Ext> PollContext<'_, E> {
             D::TxToken<'tx>,
             Option<(Ipv4Packet<&'pkt [u8]>, D::TxToken<'tx>)>,
         >,
-        Q: FnMut(&Packet, &mut Context, D::TxToken<'_>),
+        Q: FnMut(&OutgoingPacket, &mut Context, D::TxToken<'_>),
     {
         while let Some((rx_token, tx_token)) = device.receive(self.iface.context().now()) {
             rx_token.consume(|data| {
@@ -83,7 +133,11 @@ impl<E: Ext> PollContext<'_, E> {
                     return;
                 };
 
-                dispatch_phy(&reply, self.iface.context_mut(), tx_token);
+                dispatch_phy(
+                    &OutgoingPacket::new(reply),
+                    self.iface.context_mut(),
+                    tx_token,
+                );
             });
         }
     }
@@ -111,10 +165,60 @@ impl<E: Ext> PollContext<'_, E> {
             IpProtocol::Udp => {
                 self.parse_and_process_udp(&IpRepr::Ipv4(repr), pkt.payload(), &checksum_caps)
             }
+            IpProtocol::Icmp => {
+                self.parse_and_process_icmpv4(&repr, pkt.payload(), &checksum_caps);
+                None
+            }
             _ => None,
         }
     }
 
+    fn parse_and_process_icmpv4(
+        &mut self,
+        ip_repr: &Ipv4Repr,
+        ip_payload: &[u8],
+        checksum_caps: &ChecksumCapabilities,
+    ) {
+        // Parse the ICMP header. Ignore the packet if the header is ill-formed.
+        let Ok(icmp_pkt) = Icmpv4Packet::new_checked(ip_payload) else {
+            return;
+        };
+        if checksum_caps.icmpv4.rx() && !icmp_pkt.verify_checksum() {
+            return;
+        }
+
+        // TODO: Handle other ICMP messages (e.g., echo requests).
+        if icmp_pkt.msg_type() != Icmpv4Message::DstUnreachable
+            || icmp_pkt.msg_code() != Icmpv4DstUnreachable::PortUnreachable.into()
+        {
+            return;
+        }
+
+        // The message contains the IP header and (at least) the first eight bytes of the original
+        // datagram. The original datagram is truncated, so we parse the headers with the views
+        // that allow truncated payloads instead of using `Icmpv4Repr::parse`.
+        let Ok(orig_ipv4_hdr) = Ipv4Header::new_checked(icmp_pkt.data()) else {
+            return;
+        };
+        if orig_ipv4_hdr.next_header() != IpProtocol::Udp {
+            return;
+        }
+        let Ok(orig_udp_hdr) = UdpHeader::new_checked(orig_ipv4_hdr.payload()) else {
+            return;
+        };
+
+        let remote_endpoint = IpEndpoint::new(
+            IpAddress::Ipv4(orig_ipv4_hdr.dst_addr()),
+            orig_udp_hdr.dst_port(),
+        );
+        self.process_udp_port_unreachable(
+            orig_udp_hdr.src_port(),
+            remote_endpoint,
+            IpAddress::Ipv4(ip_repr.src_addr),
+            orig_udp_hdr.payload(),
+        );
+    }
+
     fn parse_and_process_tcp<'pkt>(
         &mut self,
         ip_repr: &IpRepr,
@@ -137,8 +241,10 @@ impl<E: Ext> PollContext<'_, E> {
             checksum_caps,
         )
         .ok()?;
+        // The urgent pointer is not parsed by `TcpRepr`, so we have to parse it separately.
+        let urgent_ptr = tcp_pkt.urg().then(|| tcp_pkt.urgent_at());
 
-        self.process_tcp_until_outgoing(ip_repr, &tcp_repr)
+        self.process_tcp_until_outgoing(ip_repr, &tcp_repr, urgent_ptr)
             .map(|(ip_repr, tcp_repr)| Packet::new(ip_repr, IpPayload::Tcp(tcp_repr)))
     }
 
@@ -146,15 +252,17 @@ impl<E: Ext> PollContext<'_, E> {
         &mut self,
         ip_repr: &IpRepr,
         tcp_repr: &TcpRepr,
+        urgent_ptr: Option<u16>,
     ) -> Option<(IpRepr, TcpRepr<'static>)> {
-        let (mut ip_repr, mut tcp_repr) = self.process_tcp(ip_repr, tcp_repr)?;
+        let (mut ip_repr, mut tcp_repr) = self.process_tcp(ip_repr, tcp_repr, urgent_ptr)?;
 
         loop {
             if !self.is_unicast_local(ip_repr.dst_addr()) {
                 return Some((ip_repr, tcp_repr));
             }
 
-            let (new_ip_repr, new_tcp_repr) = self.process_tcp(&ip_repr, &tcp_repr)?;
+            // Replies never carry urgent pointers.
+            let (new_ip_repr, new_tcp_repr) = self.process_tcp(&ip_repr, &tcp_repr, None)?;
             ip_repr = new_ip_repr;
             tcp_repr = new_tcp_repr;
         }
@@ -164,6 +272,7 @@ impl<E: Ext> PollContext<'_, E> {
         &mut self,
         ip_repr: &IpRepr,
         tcp_repr: &TcpRepr,
+        urgent_ptr: Option<u16>,
     ) -> Option<(IpRepr, TcpRepr<'static>)> {
         // Process packets belonging to existing connections first.
         // Note that we must do this first because SYN packets may match existing TIME-WAIT
@@ -199,7 +308,7 @@ impl<E: Ext> PollContext<'_, E> {
 
             if let Some(connection) = connection {
                 let (process_result, became_dead) =
-                    connection.process(&mut self.iface, ip_repr, tcp_repr);
+                    connection.process(&mut self.iface, ip_repr, tcp_repr, urgent_ptr);
                 if *became_dead {
                     self.actions
                         .push(SocketTableAction::DelTcpConn(*connection.connection_key()));
@@ -292,6 +401,24 @@ impl<E: Ext> PollContext<'_, E> {
         processed
     }
 
+    /// Reports that the port of the remote endpoint is unreachable to the UDP socket bound to the
+    /// local port.
+    fn process_udp_port_unreachable(
+        &self,
+        local_port: u16,
+        remote_endpoint: IpEndpoint,
+        offender: IpAddress,
+        payload: &[u8],
+    ) {
+        if let Some(socket) = self
+            .sockets
+            .udp_socket_iter()
+            .find(|socket| socket.can_process(local_port))
+        {
+            socket.on_port_unreachable(remote_endpoint, offender, payload);
+        }
+    }
+
     fn generate_icmp_unreachable<'pkt>(
         &self,
         ip_repr: &IpRepr,
@@ -356,7 +483,7 @@ impl<E: Ext> PollContext<'_, E> {
     pub(super) fn poll_egress<D, Q>(&mut self, device: &mut D, dispatch_phy: &mut Q)
     where
         D: Device + ?Sized,
-        Q: FnMut(&Packet, &mut Context, D::TxToken<'_>),
+        Q: FnMut(&OutgoingPacket, &mut Context, D::TxToken<'_>),
     {
         while let Some(tx_token) = device.transmit(self.iface.context().now()) {
             if !self.dispatch_ipv4(tx_token, dispatch_phy) {
@@ -368,7 +495,7 @@ impl<E: Ext> PollContext<'_, E> {
     fn dispatch_ipv4<T, Q>(&mut self, tx_token: T, dispatch_phy: &mut Q) -> bool
     where
         T: TxToken,
-        Q: FnMut(&Packet, &mut Context, T),
+        Q: FnMut(&OutgoingPacket, &mut Context, T),
     {
         let (did_something_tcp, tx_token) = self.dispatch_tcp(tx_token, dispatch_phy);
 
@@ -384,7 +511,7 @@ impl<E: Ext> PollContext<'_, E> {
     fn dispatch_tcp<T, Q>(&mut self, tx_token: T, dispatch_phy: &mut Q) -> (bool, Option<T>)
     where
         T: TxToken,
-        Q: FnMut(&Packet, &mut Context, T),
+        Q: FnMut(&OutgoingPacket, &mut Context, T),
     {
         let mut tx_token = Some(tx_token);
         let mut did_something = false;
@@ -400,13 +527,15 @@ impl<E: Ext> PollContext<'_, E> {
 
             let mut deferred = None;
 
-            let (reply, became_dead) =
-                TcpConnectionBg::dispatch(&socket, &mut self.iface, |iface, ip_repr, tcp_repr| {
+            let (reply, became_dead) = TcpConnectionBg::dispatch(
+                &socket,
+                &mut self.iface,
+                |iface, ip_repr, tcp_repr, urgent_ptr| {
                     let mut this = PollContext::new(iface, self.sockets, self.actions);
 
                     if !this.is_unicast_local(ip_repr.dst_addr()) {
                         dispatch_phy(
-                            &Packet::new(ip_repr.clone(), IpPayload::Tcp(*tcp_repr)),
+                            &OutgoingPacket::new_tcp(ip_repr.clone(), *tcp_repr, urgent_ptr),
                             this.iface.context_mut(),
                             tx_token.take().unwrap(),
                         );
@@ -414,24 +543,30 @@ impl<E: Ext> PollContext<'_, E> {
                     }
 
                     if !socket.can_process(tcp_repr.dst_port) {
-                        return this.process_tcp(ip_repr, tcp_repr);
+                        return this.process_tcp(ip_repr, tcp_repr, urgent_ptr);
                     }
 
                     // We cannot call `process_tcp` now because it may cause deadlocks. We will copy
                     // the packet and call `process_tcp` after releasing the socket lock.
                     deferred = Some((ip_repr.clone(), {
                         let mut data = vec![0; tcp_repr.buffer_len()];
+                        let mut tcp_pkt = TcpPacket::new_unchecked(data.as_mut_slice());
                         tcp_repr.emit(
-                            &mut TcpPacket::new_unchecked(data.as_mut_slice()),
+                            &mut tcp_pkt,
                             &ip_repr.src_addr(),
                             &ip_repr.dst_addr(),
                             &ChecksumCapabilities::ignored(),
                         );
+                        if let Some(urgent_ptr) = urgent_ptr {
+                            tcp_pkt.set_urg(true);
+                            tcp_pkt.set_urgent_at(urgent_ptr);
+                        }
                         data
                     }));
 
                     None
-                });
+                },
+            );
 
             if *became_dead {
                 self.actions
@@ -446,22 +581,26 @@ impl<E: Ext> PollContext<'_, E> {
                         &ip_payload,
                         &ChecksumCapabilities::ignored(),
                     ) {
-                        dispatch_phy(&reply, self.iface.context_mut(), tx_token.take().unwrap());
+                        dispatch_phy(
+                            &OutgoingPacket::new(reply),
+                            self.iface.context_mut(),
+                            tx_token.take().unwrap(),
+                        );
                     }
                 }
                 (None, Some((ip_repr, tcp_repr))) if !self.is_unicast_local(ip_repr.dst_addr()) => {
                     dispatch_phy(
-                        &Packet::new(ip_repr, IpPayload::Tcp(tcp_repr)),
+                        &OutgoingPacket::new_tcp(ip_repr, tcp_repr, None),
                         self.iface.context_mut(),
                         tx_token.take().unwrap(),
                     );
                 }
                 (None, Some((ip_repr, tcp_repr))) => {
                     if let Some((new_ip_repr, new_tcp_repr)) =
-                        self.process_tcp_until_outgoing(&ip_repr, &tcp_repr)
+                        self.process_tcp_until_outgoing(&ip_repr, &tcp_repr, None)
                     {
                         dispatch_phy(
-                            &Packet::new(new_ip_repr, IpPayload::Tcp(new_tcp_repr)),
+                            &OutgoingPacket::new_tcp(new_ip_repr, new_tcp_repr, None),
                             self.iface.context_mut(),
                             tx_token.take().unwrap(),
                         );
@@ -481,7 +620,7 @@ impl<E: Ext> PollContext<'_, E> {
     fn dispatch_udp<T, Q>(&mut self, tx_token: T, dispatch_phy: &mut Q) -> (bool, Option<T>)
     where
         T: TxToken,
-        Q: FnMut(&Packet, &mut Context, T),
+        Q: FnMut(&OutgoingPacket, &mut Context, T),
     {
         let mut tx_token = Some(tx_token);
         let mut did_something = false;
@@ -506,7 +645,10 @@ impl<E: Ext> PollContext<'_, E> {
 
                 if ip_repr.dst_addr().is_broadcast() || !this.is_unicast_local(ip_repr.dst_addr()) {
                     dispatch_phy(
-                        &Packet::new(ip_repr.clone(), IpPayload::Udp(*udp_repr, udp_payload)),
+                        &OutgoingPacket::new(Packet::new(
+                            ip_repr.clone(),
+                            IpPayload::Udp(*udp_repr, udp_payload),
+                        )),
                         this.iface.context_mut(),
                         tx_token.take().unwrap(),
                     );
@@ -516,9 +658,23 @@ impl<E: Ext> PollContext<'_, E> {
                 }
 
                 if !socket.can_process(udp_repr.dst_port) {
-                    // TODO: Generate the ICMP message here once we're able to handle incoming ICMP
-                    // messages.
-                    let _ = this.process_udp(ip_repr, udp_repr, udp_payload);
+                    // No ICMP messages are generated for local packets. Instead, the error is
+                    // reported directly to the sending socket, with the payload truncated as if
+                    // it were quoted in an ICMP message.
+                    if !this.process_udp(ip_repr, udp_repr, udp_payload)
+                        && !ip_repr.dst_addr().is_broadcast()
+                    {
+                        let quoted_len = icmp_reply_payload_len(
+                            UDP_HEADER_LEN + udp_payload.len(),
+                            IPV4_MIN_MTU,
+                            IPV4_HEADER_LEN,
+                        ) - UDP_HEADER_LEN;
+                        socket.on_port_unreachable(
+                            IpEndpoint::new(ip_repr.dst_addr(), udp_repr.dst_port),
+                            ip_repr.dst_addr(),
+                            &udp_payload[..quoted_len],
+                        );
+                    }
                     return;
                 }
 
@@ -544,7 +700,11 @@ impl<E: Ext> PollContext<'_, E> {
                     &ip_payload,
                     &ChecksumCapabilities::ignored(),
                 ) {
-                    dispatch_phy(&reply, self.iface.context_mut(), tx_token.take().unwrap());
+                    dispatch_phy(
+                        &OutgoingPacket::new(reply),
+                        self.iface.context_mut(),
+                        tx_token.take().unwrap(),
+                    );
                 }
             }
 
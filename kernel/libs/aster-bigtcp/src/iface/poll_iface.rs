@@ -46,6 +46,22 @@ impl<E: Ext> PollableIface<E> {
             .map(|ip_addr| ip_addr.prefix_len())
     }
 
+    pub(super) fn ipv4_cidr(&self) -> Option<smoltcp::wire::Ipv4Cidr> {
+        self.interface
+            .ip_addrs()
+            .first()
+            .map(|smoltcp::wire::IpCidr::Ipv4(ipv4_cidr)| *ipv4_cidr)
+    }
+
+    pub(super) fn set_ipv4_cidr(&mut self, cidr: Option<smoltcp::wire::Ipv4Cidr>) {
+        self.interface.update_ip_addrs(|ip_addrs| {
+            ip_addrs.clear();
+            if let Some(cidr) = cidr {
+                ip_addrs.push(smoltcp::wire::IpCidr::Ipv4(cidr)).unwrap();
+            }
+        });
+    }
+
     /// Returns the next poll time.
     pub(super) fn next_poll_at_ms(&self) -> Option<u64> {
         self.pending_conns.next_poll_at_ms()
@@ -2,9 +2,9 @@
 
 /// The configuration using for bind to a TCP/UDP port.
 pub enum BindPortConfig {
-    /// Binds to the specified non-reusable port.
-    CanReuse(u16),
     /// Binds to the specified reusable port.
+    CanReuse(u16),
+    /// Binds to the specified non-reusable port.
     Specified(u16),
     /// Allocates an ephemeral port to bind.
     Ephemeral,
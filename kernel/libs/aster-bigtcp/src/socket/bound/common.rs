@@ -98,6 +98,13 @@ impl<T: Inner<E>, E: Ext> Socket<T, E> {
     pub fn iface(&self) -> &Arc<dyn Iface<E>> {
         self.0.bound.iface()
     }
+
+    /// Sets whether the bound port can be reused by other sockets.
+    ///
+    /// See [`BoundPort::set_can_reuse`] for details.
+    pub fn set_can_reuse(&self, can_reuse: bool) {
+        self.0.bound.set_can_reuse(can_reuse);
+    }
 }
 
 define_boolean_value!(
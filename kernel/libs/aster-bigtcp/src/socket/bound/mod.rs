@@ -3,6 +3,7 @@
 mod common;
 mod tcp_conn;
 mod tcp_listen;
+mod tcp_urgent;
 mod udp;
 
 pub use common::NeedIfacePoll;
@@ -10,5 +11,5 @@ pub use tcp_conn::{ConnectState, RawTcpSocketExt, TcpConnection};
 pub(crate) use tcp_conn::{TcpConnectionBg, TcpProcessResult};
 pub use tcp_listen::TcpListener;
 pub(crate) use tcp_listen::TcpListenerBg;
-pub use udp::UdpSocket;
 pub(crate) use udp::UdpSocketBg;
+pub use udp::{PacketFilter, UdpIcmpError, UdpSocket};
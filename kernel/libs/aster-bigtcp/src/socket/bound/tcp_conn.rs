@@ -4,11 +4,16 @@ use alloc::{
     boxed::Box,
     sync::{Arc, Weak},
 };
-use core::ops::{Deref, DerefMut};
+use core::{
+    convert::Infallible,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use aster_softirq::BottomHalfDisabled;
 use ostd::sync::{SpinLock, SpinLockGuard};
 use smoltcp::{
+    iface::Context,
     socket::{tcp::State, PollAt},
     time::Duration,
     wire::{IpEndpoint, IpRepr, TcpControl, TcpRepr},
@@ -17,10 +22,11 @@ use smoltcp::{
 use super::{
     common::{Inner, NeedIfacePoll, Socket, SocketBg},
     tcp_listen::TcpListenerBg,
+    tcp_urgent::TcpUrgent,
 };
 use crate::{
     define_boolean_value,
-    errors::tcp::{ConnectError, RecvError, SendError},
+    errors::tcp::{ConnectError, RecvError, RecvUrgentError, SendError},
     ext::Ext,
     iface::{BoundPort, PollKey, PollableIfaceMut},
     socket::{
@@ -48,6 +54,43 @@ pub struct RawTcpSocketExt<E: Ext> {
     is_recv_shut: bool,
     /// Indicates if the socket is closed by a RST packet.
     is_rst_closed: bool,
+    /// Indicates if the socket is counted as a TIME-WAIT connection.
+    time_wait: TimeWait,
+    /// The states of the urgent data.
+    urgent: TcpUrgent,
+}
+
+enum TimeWait {
+    /// The socket is not in the TIME-WAIT state.
+    None,
+    /// The socket is in the TIME-WAIT state and is counted.
+    Counted(TimeWaitToken),
+    /// The socket is in the TIME-WAIT state but cannot be counted because the limit has been
+    /// reached, so it should be closed.
+    Overflowed,
+}
+
+/// The number of TCP connections in the TIME-WAIT state.
+static NUM_TIME_WAIT_CONNS: AtomicUsize = AtomicUsize::new(0);
+
+/// A token that counts a TCP connection in the TIME-WAIT state until it is dropped.
+struct TimeWaitToken(());
+
+impl TimeWaitToken {
+    fn try_new(max_conns: usize) -> Option<Self> {
+        NUM_TIME_WAIT_CONNS
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |num_conns| {
+                (num_conns < max_conns).then_some(num_conns + 1)
+            })
+            .ok()?;
+        Some(Self(()))
+    }
+}
+
+impl Drop for TimeWaitToken {
+    fn drop(&mut self) {
+        NUM_TIME_WAIT_CONNS.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 impl<E: Ext> Deref for RawTcpSocketExt<E> {
@@ -106,6 +149,24 @@ impl<E: Ext> RawTcpSocketExt<E> {
     pub fn is_rst_closed(&self) -> bool {
         self.is_rst_closed
     }
+
+    /// Returns whether the urgent data is received inline.
+    pub fn is_urgent_inline(&self) -> bool {
+        self.urgent.is_inline()
+    }
+
+    /// Returns whether the next byte to receive is the urgent data.
+    ///
+    /// This is what `sockatmark` reports.
+    pub fn is_at_urgent_mark(&self) -> bool {
+        self.urgent.is_at_mark()
+    }
+
+    /// Returns whether there is urgent data that can be received by
+    /// [`TcpConnection::recv_urgent`].
+    pub fn has_urgent_data(&self) -> bool {
+        self.urgent.has_data()
+    }
 }
 
 define_boolean_value!(
@@ -163,8 +224,11 @@ impl<E: Ext> RawTcpSocketExt<E> {
 
             if let Some(ref listener) = self.listener {
                 let mut backlog = listener.inner.backlog.lock();
+                // FIXME: Linux drops the final ACK of the handshake if the accept queue is full,
+                // so the connection stays in the SYN queue. This is impossible here because the
+                // connection is already established, so the accept queue may overflow slightly.
                 if let Some(value) = backlog.connecting.remove(this.connection_key()) {
-                    backlog.connected.push(value);
+                    backlog.connected.push_back(value);
                 }
                 listener.notify_events(SocketEvents::CAN_RECV);
             }
@@ -212,11 +276,63 @@ impl<E: Ext> RawTcpSocketExt<E> {
 
         TcpConnBecameDead::FALSE
     }
+
+    /// Checks whether the connection should be closed because it cannot stay in the TIME-WAIT
+    /// state.
+    ///
+    /// If the maximum number of TIME-WAIT connections has been reached, Linux closes the
+    /// connection instead of keeping it in the TIME-WAIT state. However, the ACK packet that
+    /// acknowledges the peer's FIN packet must be sent first. `is_ack_pending` indicates whether
+    /// such an ACK packet may be pending.
+    fn check_time_wait(&mut self, is_ack_pending: bool) -> bool {
+        if self.state() != State::TimeWait {
+            self.time_wait = TimeWait::None;
+            return false;
+        }
+
+        if matches!(self.time_wait, TimeWait::None) {
+            self.time_wait = match TimeWaitToken::try_new(E::max_time_wait_conns()) {
+                Some(token) => TimeWait::Counted(token),
+                None => TimeWait::Overflowed,
+            };
+        }
+
+        matches!(self.time_wait, TimeWait::Overflowed) && !is_ack_pending
+    }
+
+    /// Records the urgent pointer of an incoming packet before processing the packet.
+    fn pre_process_urgent(&mut self, tcp_repr: &TcpRepr, urgent_ptr: Option<u16>) {
+        self.urgent.on_recv(&mut self.socket, tcp_repr, urgent_ptr);
+    }
+
+    /// Checks whether new urgent data has arrived after processing incoming packets.
+    fn post_process_urgent(&mut self) -> SocketEvents {
+        if self.urgent.check_arrival(&mut self.socket) {
+            SocketEvents::URGENT
+        } else {
+            SocketEvents::empty()
+        }
+    }
+
+    /// Closes the connection immediately without sending any packets.
+    ///
+    /// The connection will be dead after this method returns.
+    fn close_silently(&mut self, cx: &mut Context) {
+        // This is a very silly approach to force the socket to go dead. The first `abort` call
+        // changes the socket state to `CLOSED`, the second `dispatch` call sets the tuple
+        // (local/remote endpoint) to none. So this socket will not accept or send any packets
+        // in the future.
+        self.abort();
+        self.dispatch(cx, |_, _| Ok::<(), Infallible>(())).unwrap();
+
+        self.time_wait = TimeWait::None;
+    }
 }
 
 impl<E: Ext> TcpConnectionInner<E> {
     pub(super) fn new(
         socket: Box<RawTcpSocket>,
+        urgent: TcpUrgent,
         listener: Option<Arc<TcpListenerBg<E>>>,
         weak_self: &Weak<TcpConnectionBg<E>>,
     ) -> Self {
@@ -235,6 +351,8 @@ impl<E: Ext> TcpConnectionInner<E> {
             has_connected: false,
             is_recv_shut: false,
             is_rst_closed: false,
+            time_wait: TimeWait::None,
+            urgent,
         };
 
         TcpConnectionInner {
@@ -323,8 +441,10 @@ impl<E: Ext> TcpConnection<E> {
             socket
         };
 
-        let connection =
-            Self::new_cyclic(bound, |weak| TcpConnectionInner::new(socket, None, weak));
+        let urgent = TcpUrgent::new(option.is_urgent_inline);
+        let connection = Self::new_cyclic(bound, |weak| {
+            TcpConnectionInner::new(socket, urgent, None, weak)
+        });
         interface.update_next_poll_at_ms(&connection.0, PollAt::Now);
         connection.init_observer(observer);
 
@@ -371,6 +491,23 @@ impl<E: Ext> TcpConnection<E> {
     ///
     /// Polling the iface _may_ be required after this method succeeds.
     pub fn send<F, R>(&self, f: F) -> Result<(R, NeedIfacePoll), SendError>
+    where
+        F: FnOnce(&mut [u8]) -> (usize, R),
+    {
+        self.do_send(f, false)
+    }
+
+    /// Sends some data and marks the last byte as the urgent data.
+    ///
+    /// Polling the iface _may_ be required after this method succeeds.
+    pub fn send_urgent<F, R>(&self, f: F) -> Result<(R, NeedIfacePoll), SendError>
+    where
+        F: FnOnce(&mut [u8]) -> (usize, R),
+    {
+        self.do_send(f, true)
+    }
+
+    fn do_send<F, R>(&self, f: F, is_urgent: bool) -> Result<(R, NeedIfacePoll), SendError>
     where
         F: FnOnce(&mut [u8]) -> (usize, R),
     {
@@ -383,7 +520,13 @@ impl<E: Ext> TcpConnection<E> {
             socket.is_rst_closed = false;
             return Err(SendError::ConnReset);
         }
-        let result = socket.send(f)?;
+        let mut sent_len = 0;
+        let result = socket.send(|buffer| {
+            let (len, result) = f(buffer);
+            sent_len = len;
+            (len, result)
+        })?;
+        socket.urgent.on_send(sent_len, is_urgent);
 
         let poll_at = socket.poll_at(iface.context_mut());
         let need_poll = iface.update_next_poll_at_ms(&self.0, poll_at);
@@ -406,7 +549,12 @@ impl<E: Ext> TcpConnection<E> {
         if socket.is_recv_shut && socket.recv_queue() == 0 {
             return Err(RecvError::Finished);
         }
-        let result = match socket.recv(f) {
+        let RawTcpSocketExt {
+            socket: raw_socket,
+            urgent,
+            ..
+        } = &mut *socket;
+        let result = match urgent.recv(raw_socket, f) {
             Err(_) if socket.is_rst_closed => {
                 socket.is_rst_closed = false;
                 return Err(RecvError::ConnReset);
@@ -420,6 +568,16 @@ impl<E: Ext> TcpConnection<E> {
         Ok((result, need_poll))
     }
 
+    /// Receives the urgent data.
+    ///
+    /// If `is_peek` is true, the urgent data will not be consumed.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn recv_urgent(&self, is_peek: bool) -> Result<u8, RecvUrgentError> {
+        let mut socket = self.0.inner.lock();
+        socket.urgent.recv_urgent(is_peek)
+    }
+
     /// Checks if the socket is closed by a RST packet and clears the flag.
     ///
     /// This flag is set when the socket is closed by a RST packet, and cleared when the connection
@@ -533,10 +691,25 @@ impl<E: Ext> RawTcpSetOption for TcpConnection<E> {
         iface.update_next_poll_at_ms(&self.0, poll_at)
     }
 
+    fn set_timeout(&self, timeout: Option<Duration>) -> NeedIfacePoll {
+        let mut iface = self.iface().common().interface();
+        let mut socket = self.0.inner.lock();
+
+        socket.set_timeout(timeout);
+
+        let poll_at = socket.poll_at(iface.context_mut());
+        iface.update_next_poll_at_ms(&self.0, poll_at)
+    }
+
     fn set_nagle_enabled(&self, enabled: bool) {
         let mut socket = self.0.inner.lock();
         socket.set_nagle_enabled(enabled);
     }
+
+    fn set_urgent_inline(&self, is_inline: bool) {
+        let mut socket = self.0.inner.lock();
+        socket.urgent.set_inline(is_inline);
+    }
 }
 
 impl<E: Ext> TcpConnectionBg<E> {
@@ -563,6 +736,7 @@ impl<E: Ext> TcpConnectionBg<E> {
         iface: &mut PollableIfaceMut<E>,
         ip_repr: &IpRepr,
         tcp_repr: &TcpRepr,
+        urgent_ptr: Option<u16>,
     ) -> (TcpProcessResult, TcpConnBecameDead) {
         let mut socket = self.inner.lock();
 
@@ -591,16 +765,7 @@ impl<E: Ext> TcpConnectionBg<E> {
             && tcp_repr.control == TcpControl::Syn
             && tcp_repr.ack_number.is_none()
         {
-            // This is a very silly approach to force the socket to go dead. The first `abort` call
-            // changes the socket state to `CLOSED`, the second `dispatch` call sets the tuple
-            // (local/remote endpoint) to none. So this socket will not accept or send any packets
-            // in the future.
-            socket.abort();
-            socket
-                .dispatch(iface.context_mut(), |_, _| {
-                    Ok::<(), core::convert::Infallible>(())
-                })
-                .unwrap();
+            socket.close_silently(iface.context_mut());
 
             iface.update_next_poll_at_ms(self, PollAt::Ingress);
             return (TcpProcessResult::NotProcessed, TcpConnBecameDead::TRUE);
@@ -613,15 +778,25 @@ impl<E: Ext> TcpConnectionBg<E> {
         // to be queued.
         let mut events = SocketEvents::CAN_RECV | SocketEvents::CAN_SEND;
 
+        socket.pre_process_urgent(tcp_repr, urgent_ptr);
         let result = match socket.process(iface.context_mut(), ip_repr, tcp_repr) {
             None => TcpProcessResult::Processed,
             Some((ip_repr, tcp_repr)) => TcpProcessResult::ProcessedWithReply(ip_repr, tcp_repr),
         };
+        events |= socket.post_process_urgent();
 
-        let (state_events, became_dead) =
+        let (state_events, mut became_dead) =
             socket.check_state(self, old_state, old_recv_queue, is_rst);
         events |= state_events;
 
+        // If the FIN packet is not acknowledged by the reply, the ACK packet will be sent later.
+        let is_ack_pending =
+            tcp_repr.control == TcpControl::Fin && matches!(result, TcpProcessResult::Processed);
+        if socket.check_time_wait(is_ack_pending) {
+            socket.close_silently(iface.context_mut());
+            became_dead = TcpConnBecameDead::TRUE;
+        }
+
         self.notify_events(events);
 
         let poll_at = socket.poll_at(iface.context_mut());
@@ -637,7 +812,12 @@ impl<E: Ext> TcpConnectionBg<E> {
         dispatch: D,
     ) -> (Option<(IpRepr, TcpRepr<'static>)>, TcpConnBecameDead)
     where
-        D: FnOnce(PollableIfaceMut<E>, &IpRepr, &TcpRepr) -> Option<(IpRepr, TcpRepr<'static>)>,
+        D: FnOnce(
+            PollableIfaceMut<E>,
+            &IpRepr,
+            &TcpRepr,
+            Option<u16>,
+        ) -> Option<(IpRepr, TcpRepr<'static>)>,
     {
         let mut socket = self.inner.lock();
 
@@ -647,29 +827,53 @@ impl<E: Ext> TcpConnectionBg<E> {
         let mut events = SocketEvents::empty();
 
         let mut reply = None;
+        let mut is_sent = false;
         let (cx, pending) = iface.inner_mut();
-        socket
+        let RawTcpSocketExt {
+            socket: raw_socket,
+            urgent,
+            ..
+        } = &mut *socket;
+        raw_socket
             .dispatch(cx, |cx, (ip_repr, tcp_repr)| {
-                reply = dispatch(PollableIfaceMut::new(cx, pending), &ip_repr, &tcp_repr);
+                let urgent_ptr = urgent.on_dispatch(&tcp_repr);
+                reply = dispatch(
+                    PollableIfaceMut::new(cx, pending),
+                    &ip_repr,
+                    &tcp_repr,
+                    urgent_ptr,
+                );
+                is_sent = true;
                 Ok::<(), ()>(())
             })
             .unwrap();
 
         // `dispatch` can return a packet in response to the generated packet. If the socket
         // accepts the packet, we can process it directly.
+        let mut is_fin_received = false;
         while let Some((ref ip_repr, ref tcp_repr)) = reply {
             if !socket.accepts(iface.context_mut(), ip_repr, tcp_repr) {
                 break;
             }
             is_rst |= tcp_repr.control == TcpControl::Rst;
+            is_fin_received |= tcp_repr.control == TcpControl::Fin;
             events |= SocketEvents::CAN_RECV | SocketEvents::CAN_SEND;
+            socket.pre_process_urgent(tcp_repr, None);
             reply = socket.process(iface.context_mut(), ip_repr, tcp_repr);
         }
+        events |= socket.post_process_urgent();
 
-        let (state_events, became_dead) =
+        let (state_events, mut became_dead) =
             socket.check_state(self, old_state, old_recv_queue, is_rst);
         events |= state_events;
 
+        // In the TIME-WAIT state, the packet that is sent can only be the ACK packet.
+        let is_ack_pending = !is_sent || (is_fin_received && reply.is_none());
+        if socket.check_time_wait(is_ack_pending) {
+            socket.close_silently(iface.context_mut());
+            became_dead = TcpConnBecameDead::TRUE;
+        }
+
         self.notify_events(events);
 
         let poll_at = socket.poll_at(iface.context_mut());
@@ -1,6 +1,10 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::{boxed::Box, collections::btree_map::BTreeMap, sync::Arc, vec::Vec};
+use alloc::{
+    boxed::Box,
+    collections::{btree_map::BTreeMap, VecDeque},
+    sync::Arc,
+};
 
 use aster_softirq::BottomHalfDisabled;
 use ostd::sync::SpinLock;
@@ -13,11 +17,12 @@ use smoltcp::{
 use super::{
     common::{Inner, NeedIfacePoll, Socket, SocketBg},
     tcp_conn::{TcpConnection, TcpConnectionBg, TcpConnectionInner, TcpProcessResult},
+    tcp_urgent::TcpUrgent,
 };
 use crate::{
     errors::tcp::ListenError,
     ext::Ext,
-    iface::{BindPortConfig, BoundPort, PollableIfaceMut},
+    iface::{BoundPort, PollableIfaceMut},
     socket::{
         option::{RawTcpOption, RawTcpSetOption},
         unbound::{new_tcp_socket, RawTcpSocket},
@@ -29,9 +34,27 @@ pub type TcpListener<E> = Socket<TcpListenerInner<E>, E>;
 
 pub struct TcpBacklog<E: Ext> {
     socket: Box<RawTcpSocket>,
+    /// Whether the urgent data is received inline by new connections.
+    is_urgent_inline: bool,
     max_conn: usize,
+    /// The SYN queue, i.e., the connections whose three-way handshakes are in progress.
     pub(super) connecting: BTreeMap<ConnectionKey, TcpConnection<E>>,
-    pub(super) connected: Vec<TcpConnection<E>>,
+    /// The accept queue, i.e., the established connections that are waiting to be accepted.
+    pub(super) connected: VecDeque<TcpConnection<E>>,
+}
+
+impl<E: Ext> TcpBacklog<E> {
+    /// Returns whether a new connection request should be dropped.
+    ///
+    /// The SYN queue and the accept queue are limited separately. Like Linux, the accept queue is
+    /// considered full only if its length _exceeds_ `max_conn`, so `listen(fd, 0)` still allows
+    /// one connection. Linux falls back to SYN cookies if the SYN queue is full, which are not
+    /// supported here, so the SYN queue is limited in the same way instead.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/ipv4/tcp_input.c#L7216>
+    fn is_full(&self) -> bool {
+        self.connecting.len() > self.max_conn || self.connected.len() > self.max_conn
+    }
 }
 
 /// States needed by [`TcpListenerBg`].
@@ -101,9 +124,10 @@ impl<E: Ext> TcpListener<E> {
         let inner = {
             let backlog = TcpBacklog {
                 socket,
+                is_urgent_inline: option.is_urgent_inline,
                 max_conn,
                 connecting: BTreeMap::new(),
-                connected: Vec::new(),
+                connected: VecDeque::new(),
             };
 
             TcpListenerInner::new(backlog, listener_key)
@@ -117,13 +141,22 @@ impl<E: Ext> TcpListener<E> {
         Ok(listener)
     }
 
+    /// Sets the maximum number of pending connections, e.g., when `listen` is called again.
+    ///
+    /// The connections that are already pending are not affected.
+    pub fn set_max_conn(&self, max_conn: usize) {
+        self.0.inner.backlog.lock().max_conn = max_conn;
+    }
+
     /// Accepts a TCP connection.
     ///
+    /// The connections are accepted in the order in which their handshakes complete.
+    ///
     /// Polling the iface is _not_ required after this method succeeds.
     pub fn accept(&self) -> Option<(TcpConnection<E>, IpEndpoint)> {
         let accepted = {
             let mut backlog = self.0.inner.backlog.lock();
-            backlog.connected.pop()?
+            backlog.connected.pop_front()?
         };
 
         let remote_endpoint = {
@@ -168,6 +201,17 @@ impl<E: Ext> TcpListener<E> {
         connecting.values().for_each(|socket| socket.reset());
         connected.iter().for_each(|socket| socket.reset());
     }
+
+    /// Closes the listener, but keeps the port bound.
+    ///
+    /// The returned [`BoundPort`] can be used to listen again.
+    ///
+    /// Polling the iface is _always_ required after this method succeeds.
+    pub fn close_and_keep_port(self) -> BoundPort<E> {
+        let bound = self.0.bound.inherit();
+        self.close();
+        bound
+    }
 }
 
 impl<E: Ext> RawTcpSetOption for TcpListener<E> {
@@ -178,10 +222,22 @@ impl<E: Ext> RawTcpSetOption for TcpListener<E> {
         NeedIfacePoll::FALSE
     }
 
+    fn set_timeout(&self, timeout: Option<Duration>) -> NeedIfacePoll {
+        let mut backlog = self.0.inner.backlog.lock();
+        backlog.socket.set_timeout(timeout);
+
+        NeedIfacePoll::FALSE
+    }
+
     fn set_nagle_enabled(&self, enabled: bool) {
         let mut backlog = self.0.inner.backlog.lock();
         backlog.socket.set_nagle_enabled(enabled);
     }
+
+    fn set_urgent_inline(&self, is_inline: bool) {
+        let mut backlog = self.0.inner.backlog.lock();
+        backlog.is_urgent_inline = is_inline;
+    }
 }
 
 impl<E: Ext> TcpListenerBg<E> {
@@ -207,10 +263,9 @@ impl<E: Ext> TcpListenerBg<E> {
             return (TcpProcessResult::NotProcessed, None);
         }
 
-        // FIXME: According to the Linux implementation, `max_conn` is the upper bound of
-        // `connected.len()`. We currently limit it to `connected.len() + connecting.len()` for
-        // simplicity.
-        if backlog.connected.len() + backlog.connecting.len() >= backlog.max_conn {
+        // The request is silently dropped, so the peer will retransmit its SYN later. Note that
+        // we check this before processing the packet, so no connection is allocated for it.
+        if backlog.is_full() {
             return (TcpProcessResult::Processed, None);
         }
 
@@ -233,19 +288,16 @@ impl<E: Ext> TcpListenerBg<E> {
             socket
         };
 
-        let conn = TcpConnection::new_cyclic(
-            self.bound
-                .iface()
-                .bind(BindPortConfig::CanReuse(self.bound.port()))
-                .unwrap(),
-            |weak| {
-                TcpConnectionInner::new(
-                    core::mem::replace(&mut backlog.socket, new_socket),
-                    Some(self.clone()),
-                    weak,
-                )
-            },
-        );
+        let urgent = TcpUrgent::new_passive(backlog.is_urgent_inline, tcp_repr.seq_number);
+
+        let conn = TcpConnection::new_cyclic(self.bound.inherit(), |weak| {
+            TcpConnectionInner::new(
+                core::mem::replace(&mut backlog.socket, new_socket),
+                urgent,
+                Some(self.clone()),
+                weak,
+            )
+        });
         let conn_bg = conn.inner().clone();
 
         let old_conn = backlog.connecting.insert(*conn_bg.connection_key(), conn);
@@ -0,0 +1,273 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::vec;
+
+use smoltcp::wire::{TcpControl, TcpRepr, TcpSeqNumber};
+
+use crate::{errors::tcp::RecvUrgentError, socket::unbound::RawTcpSocket};
+
+/// The states of the TCP urgent data (a.k.a. the out-of-band data).
+///
+/// smoltcp knows nothing about the urgent data, so the urgent pointers are tracked here in terms
+/// of the sequence numbers. Like Linux, the urgent pointer points to the byte _after_ the urgent
+/// byte (i.e., the BSD interpretation), and only the last urgent byte is remembered.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/ipv4/tcp_input.c#L5777>
+pub(super) struct TcpUrgent {
+    /// Whether the urgent byte is left in the normal data stream (i.e., `SO_OOBINLINE`).
+    is_inline: bool,
+    /// The sequence number of the next byte that will be queued for sending.
+    ///
+    /// This is `None` until the initial sequence number of the connection is known.
+    write_seq: Option<TcpSeqNumber>,
+    /// The sequence number after the last urgent byte to send (i.e., `SND.UP`).
+    ///
+    /// This is `None` if the urgent data has been acknowledged by the peer.
+    snd_up: Option<TcpSeqNumber>,
+    /// The sequence number of the next byte that will be read by the user.
+    ///
+    /// This is `None` until the initial sequence number of the peer is known.
+    copied_seq: Option<TcpSeqNumber>,
+    /// The sequence number of the urgent byte to receive and the state of the byte.
+    ///
+    /// This is `None` if there is no urgent byte or if the urgent byte has been passed by the
+    /// reader.
+    rcv_urg: Option<(TcpSeqNumber, UrgentByte)>,
+}
+
+#[derive(Clone, Copy)]
+enum UrgentByte {
+    /// The urgent pointer is received, but the urgent byte has not arrived yet.
+    NotYet,
+    /// The urgent byte has arrived.
+    Valid(u8),
+    /// The urgent byte has been read out of band.
+    Read,
+}
+
+impl TcpUrgent {
+    /// Creates the states for an actively opened connection.
+    pub(super) fn new(is_inline: bool) -> Self {
+        Self {
+            is_inline,
+            write_seq: None,
+            snd_up: None,
+            copied_seq: None,
+            rcv_urg: None,
+        }
+    }
+
+    /// Creates the states for a passively opened connection, whose SYN packet has the sequence
+    /// number `syn_seq`.
+    pub(super) fn new_passive(is_inline: bool, syn_seq: TcpSeqNumber) -> Self {
+        Self {
+            copied_seq: Some(syn_seq + 1),
+            ..Self::new(is_inline)
+        }
+    }
+
+    pub(super) fn is_inline(&self) -> bool {
+        self.is_inline
+    }
+
+    pub(super) fn set_inline(&mut self, is_inline: bool) {
+        self.is_inline = is_inline;
+    }
+
+    /// Returns whether the next byte to read is the urgent byte.
+    pub(super) fn is_at_mark(&self) -> bool {
+        match (self.rcv_urg, self.copied_seq) {
+            (Some((urg_seq, _)), Some(copied_seq)) => urg_seq == copied_seq,
+            _ => false,
+        }
+    }
+
+    /// Returns whether the urgent byte has arrived and has not been read out of band.
+    pub(super) fn has_data(&self) -> bool {
+        matches!(self.rcv_urg, Some((_, UrgentByte::Valid(_))))
+    }
+
+    /// Records that `len` bytes are queued for sending.
+    ///
+    /// If `is_urgent` is true, the last byte will be sent as the urgent byte.
+    pub(super) fn on_send(&mut self, len: usize, is_urgent: bool) {
+        let Some(write_seq) = self.write_seq.as_mut() else {
+            return;
+        };
+
+        *write_seq = *write_seq + len;
+        if is_urgent && len > 0 {
+            self.snd_up = Some(*write_seq);
+        }
+    }
+
+    /// Returns the urgent pointer of an outgoing packet.
+    pub(super) fn on_dispatch(&mut self, tcp_repr: &TcpRepr) -> Option<u16> {
+        if tcp_repr.control == TcpControl::Syn {
+            self.write_seq.get_or_insert(tcp_repr.seq_number + 1);
+            return None;
+        }
+
+        let offset = self.snd_up?.0.wrapping_sub(tcp_repr.seq_number.0);
+        if offset <= 0 {
+            return None;
+        }
+
+        // If the urgent byte is too far away, Linux sends the maximum offset to let the peer
+        // enter the urgent mode as early as possible. We do the same.
+        Some(offset.min(u16::MAX as i32) as u16)
+    }
+
+    /// Records the urgent pointer of an incoming packet.
+    ///
+    /// This method should be called _before_ the packet is processed by the socket.
+    pub(super) fn on_recv(
+        &mut self,
+        socket: &mut RawTcpSocket,
+        tcp_repr: &TcpRepr,
+        urgent_ptr: Option<u16>,
+    ) {
+        if let (Some(snd_up), Some(ack_number)) = (self.snd_up, tcp_repr.ack_number) {
+            if ack_number >= snd_up {
+                self.snd_up = None;
+            }
+        }
+
+        if tcp_repr.control == TcpControl::Syn {
+            self.copied_seq.get_or_insert(tcp_repr.seq_number + 1);
+            return;
+        }
+
+        let (Some(urgent_ptr), Some(copied_seq)) = (urgent_ptr, self.copied_seq) else {
+            return;
+        };
+
+        let urg_seq = tcp_repr.seq_number + urgent_ptr.saturating_sub(1) as usize;
+
+        // Ignore the urgent pointer if the urgent byte has already been received.
+        let rcv_nxt = copied_seq + socket.recv_queue();
+        if urg_seq < rcv_nxt {
+            return;
+        }
+
+        // Ignore the urgent pointer if we already have the same (or a newer) one.
+        if let Some((old_urg_seq, _)) = self.rcv_urg {
+            if urg_seq <= old_urg_seq {
+                return;
+            }
+
+            // If the reader has stopped before the old urgent byte, the byte must be discarded
+            // now. Otherwise, it would be read as normal data once its mark is forgotten.
+            if old_urg_seq == copied_seq && !self.is_inline {
+                self.skip_urgent_byte(socket);
+            }
+        }
+
+        self.rcv_urg = Some((urg_seq, UrgentByte::NotYet));
+    }
+
+    /// Checks whether the urgent byte has arrived.
+    ///
+    /// This method should be called _after_ incoming packets are processed by the socket. It
+    /// returns true if the urgent byte is newly available.
+    pub(super) fn check_arrival(&mut self, socket: &mut RawTcpSocket) -> bool {
+        let (Some((urg_seq, UrgentByte::NotYet)), Some(copied_seq)) =
+            (self.rcv_urg, self.copied_seq)
+        else {
+            return false;
+        };
+
+        let offset = urg_seq.0.wrapping_sub(copied_seq.0) as usize;
+        if offset >= socket.recv_queue() {
+            return false;
+        }
+
+        // This copies the bytes before the urgent byte, but it happens only once per urgent byte.
+        let mut buffer = vec![0; offset + 1];
+        if socket.peek_slice(&mut buffer) != Ok(offset + 1) {
+            return false;
+        }
+
+        self.rcv_urg = Some((urg_seq, UrgentByte::Valid(buffer[offset])));
+        true
+    }
+
+    /// Receives the normal data.
+    ///
+    /// Like Linux, the reader never passes the urgent mark in a single call. The urgent byte is
+    /// skipped if it is not inline.
+    pub(super) fn recv<F, R>(
+        &mut self,
+        socket: &mut RawTcpSocket,
+        f: F,
+    ) -> Result<R, smoltcp::socket::tcp::RecvError>
+    where
+        F: FnOnce(&mut [u8]) -> (usize, R),
+    {
+        let mut limit = usize::MAX;
+
+        if let (Some((urg_seq, urg_byte)), Some(copied_seq)) = (self.rcv_urg, self.copied_seq) {
+            let offset = urg_seq.0.wrapping_sub(copied_seq.0) as usize;
+            if offset != 0 {
+                limit = offset;
+            } else if !self.is_inline && !matches!(urg_byte, UrgentByte::NotYet) {
+                self.skip_urgent_byte(socket);
+            }
+        }
+
+        let mut len = 0;
+        let result = socket.recv(|buffer| {
+            let max_len = buffer.len().min(limit);
+            let (recv_len, result) = f(&mut buffer[..max_len]);
+            len = recv_len;
+            (recv_len, result)
+        })?;
+        self.on_consumed(len);
+
+        Ok(result)
+    }
+
+    /// Receives the urgent byte out of band.
+    ///
+    /// If `is_peek` is true, the urgent byte will be left for subsequent reads.
+    pub(super) fn recv_urgent(&mut self, is_peek: bool) -> Result<u8, RecvUrgentError> {
+        if self.is_inline {
+            return Err(RecvUrgentError::NoData);
+        }
+
+        match self.rcv_urg {
+            Some((urg_seq, UrgentByte::Valid(byte))) => {
+                if !is_peek {
+                    self.rcv_urg = Some((urg_seq, UrgentByte::Read));
+                }
+                Ok(byte)
+            }
+            Some((_, UrgentByte::NotYet)) => Err(RecvUrgentError::NotArrived),
+            Some((_, UrgentByte::Read)) | None => Err(RecvUrgentError::NoData),
+        }
+    }
+
+    /// Discards the urgent byte at the head of the receive buffer.
+    fn skip_urgent_byte(&mut self, socket: &mut RawTcpSocket) {
+        if let Ok(len) = socket.recv(|buffer| {
+            let len = buffer.len().min(1);
+            (len, len)
+        }) {
+            self.on_consumed(len);
+        }
+    }
+
+    fn on_consumed(&mut self, len: usize) {
+        let Some(copied_seq) = self.copied_seq.as_mut() else {
+            return;
+        };
+
+        *copied_seq = *copied_seq + len;
+        if self
+            .rcv_urg
+            .is_some_and(|(urg_seq, _)| urg_seq < *copied_seq)
+        {
+            self.rcv_urg = None;
+        }
+    }
+}
@@ -1,14 +1,17 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::{boxed::Box, sync::Arc};
-use core::sync::atomic::{AtomicBool, Ordering};
+use alloc::{boxed::Box, collections::VecDeque, sync::Arc, vec::Vec};
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
 
 use aster_softirq::BottomHalfDisabled;
 use ostd::sync::SpinLock;
 use smoltcp::{
     iface::Context,
     socket::udp::UdpMetadata,
-    wire::{IpRepr, UdpRepr},
+    wire::{IpAddress, IpEndpoint, IpRepr, UdpRepr, UDP_HEADER_LEN},
 };
 
 use super::common::{Inner, Socket, SocketBg};
@@ -25,8 +28,50 @@ pub type UdpSocket<E> = Socket<UdpSocketInner, E>;
 pub struct UdpSocketInner {
     socket: SpinLock<Box<RawUdpSocket>, BottomHalfDisabled>,
     need_dispatch: AtomicBool,
+    /// The times when the packets in the receive buffer of [`RawUdpSocket`] were received.
+    ///
+    /// Lock order: `socket` first, `recv_times` second
+    recv_times: SpinLock<VecDeque<Duration>, BottomHalfDisabled>,
+    /// The remote endpoint that is reported to be unreachable by an ICMP message.
+    unreachable_endpoint: SpinLock<Option<IpEndpoint>, BottomHalfDisabled>,
+    /// Whether the ICMP errors are queued in `error_queue` (i.e., `IP_RECVERR`).
+    is_recv_err: AtomicBool,
+    /// The ICMP errors that have not been received by the user.
+    ///
+    /// Lock order: `error_queue` first, `unreachable_endpoint` second
+    error_queue: SpinLock<VecDeque<UdpIcmpError>, BottomHalfDisabled>,
+    /// The filter of the incoming packets.
+    filter: SpinLock<Option<Arc<dyn PacketFilter>>, BottomHalfDisabled>,
+}
+
+/// A filter of the incoming packets of a socket, e.g., a BPF socket filter.
+pub trait PacketFilter: Send + Sync {
+    /// Filters an incoming packet and returns the number of bytes to keep.
+    ///
+    /// The packet consists of the transport-layer header and the payload. If the returned
+    /// length is zero, the packet is dropped. Otherwise, the packet is truncated to the returned
+    /// length if it is longer.
+    fn filter(&self, header: &[u8], payload: &[u8]) -> usize;
 }
 
+/// An ICMP error reported for a packet sent by a UDP socket.
+///
+/// Currently, only the ICMP port unreachable messages are reported.
+#[derive(Debug, Clone)]
+pub struct UdpIcmpError {
+    /// The remote endpoint to which the packet was sent.
+    pub remote_endpoint: IpEndpoint,
+    /// The address of the host that generated the ICMP message.
+    pub offender: IpAddress,
+    /// The payload of the packet, which is truncated to what is quoted in the ICMP message.
+    pub payload: Vec<u8>,
+    /// The time when the ICMP message was received.
+    pub time: Duration,
+}
+
+/// The maximum number of ICMP errors that can be queued in a UDP socket.
+const ERROR_QUEUE_LEN: usize = 64;
+
 impl<E: Ext> Inner<E> for UdpSocketInner {
     type Observer = E::UdpEventObserver;
 
@@ -55,6 +100,24 @@ impl<E: Ext> UdpSocketBg<E> {
             return false;
         }
 
+        let filter = self.inner.filter.lock().clone();
+        let udp_payload = if let Some(filter) = filter {
+            let header = udp_header_of(udp_repr, udp_payload.len());
+            let keep_len = filter.filter(&header, udp_payload);
+            // The packet is dropped, but it is still considered processed by the socket.
+            if keep_len == 0 {
+                return true;
+            }
+            &udp_payload[..keep_len
+                .saturating_sub(UDP_HEADER_LEN)
+                .min(udp_payload.len())]
+        } else {
+            udp_payload
+        };
+
+        let mut recv_times = self.inner.recv_times.lock();
+        let old_recv_queue = socket.recv_queue();
+
         socket.process(
             cx,
             smoltcp::phy::PacketMeta::default(),
@@ -63,11 +126,50 @@ impl<E: Ext> UdpSocketBg<E> {
             udp_payload,
         );
 
+        // The packet is silently dropped if the receive buffer is full. Note that an empty packet
+        // does not change the number of the queued bytes, but takes a slot of the packet metadata.
+        let is_queued = if udp_payload.is_empty() {
+            recv_times.len() < socket.packet_recv_capacity()
+        } else {
+            socket.recv_queue() > old_recv_queue
+        };
+        if is_queued {
+            recv_times.push_back(E::now());
+        }
+        drop(recv_times);
+
         self.notify_events(SocketEvents::CAN_RECV);
 
         true
     }
 
+    /// Notifies that the port of the remote endpoint is unreachable.
+    ///
+    /// This happens when an ICMP port unreachable message is received for a packet sent to the
+    /// remote endpoint. `offender` is the host that generated the ICMP message, and `payload` is
+    /// the (possibly truncated) payload of the packet. Note that this method does not lock the
+    /// inner [`RawUdpSocket`].
+    pub(crate) fn on_port_unreachable(
+        &self,
+        remote_endpoint: IpEndpoint,
+        offender: IpAddress,
+        payload: &[u8],
+    ) {
+        let mut error_queue = self.inner.error_queue.lock();
+        if self.inner.is_recv_err.load(Ordering::Relaxed) && error_queue.len() < ERROR_QUEUE_LEN {
+            error_queue.push_back(UdpIcmpError {
+                remote_endpoint,
+                offender,
+                payload: payload.to_vec(),
+                time: E::now(),
+            });
+        }
+        *self.inner.unreachable_endpoint.lock() = Some(remote_endpoint);
+        drop(error_queue);
+
+        self.notify_events(SocketEvents::ERROR);
+    }
+
     /// Tries to generate an outgoing packet and dispatches the generated packet.
     pub(crate) fn dispatch<D>(&self, cx: &mut Context, dispatch: D)
     where
@@ -98,6 +200,17 @@ impl<E: Ext> UdpSocketBg<E> {
     }
 }
 
+/// Builds the UDP header of an incoming packet for [`PacketFilter`]s.
+///
+/// The checksum has been verified and is therefore left as zero.
+fn udp_header_of(udp_repr: &UdpRepr, payload_len: usize) -> [u8; UDP_HEADER_LEN] {
+    let mut header = [0u8; UDP_HEADER_LEN];
+    header[0..2].copy_from_slice(&udp_repr.src_port.to_be_bytes());
+    header[2..4].copy_from_slice(&udp_repr.dst_port.to_be_bytes());
+    header[4..6].copy_from_slice(&((UDP_HEADER_LEN + payload_len) as u16).to_be_bytes());
+    header
+}
+
 impl<E: Ext> UdpSocket<E> {
     /// Binds to a specified endpoint.
     ///
@@ -123,6 +236,11 @@ impl<E: Ext> UdpSocket<E> {
         let inner = UdpSocketInner {
             socket: SpinLock::new(socket),
             need_dispatch: AtomicBool::new(false),
+            recv_times: SpinLock::new(VecDeque::new()),
+            unreachable_endpoint: SpinLock::new(None),
+            is_recv_err: AtomicBool::new(false),
+            error_queue: SpinLock::new(VecDeque::new()),
+            filter: SpinLock::new(None),
         };
 
         let socket = Self::new(bound, inner);
@@ -169,19 +287,85 @@ impl<E: Ext> UdpSocket<E> {
 
     /// Receives some data.
     ///
+    /// `f` is called with the data, the metadata, and the time when the data were received.
+    ///
     /// Polling the iface is _not_ required after this method succeeds.
     pub fn recv<F, R>(&self, f: F) -> Result<R, smoltcp::socket::udp::RecvError>
     where
-        F: FnOnce(&[u8], UdpMetadata) -> R,
+        F: FnOnce(&[u8], UdpMetadata, Duration) -> R,
     {
         let mut socket = self.0.inner.socket.lock();
 
         let (data, meta) = socket.recv()?;
-        let result = f(data, meta);
+        let time = self
+            .0
+            .inner
+            .recv_times
+            .lock()
+            .pop_front()
+            .unwrap_or_default();
+        let result = f(data, meta, time);
 
         Ok(result)
     }
 
+    /// Returns the remote endpoint that is reported to be unreachable, if any.
+    pub fn unreachable_endpoint(&self) -> Option<IpEndpoint> {
+        *self.0.inner.unreachable_endpoint.lock()
+    }
+
+    /// Takes the remote endpoint that is reported to be unreachable, if any.
+    pub fn take_unreachable_endpoint(&self) -> Option<IpEndpoint> {
+        self.0.inner.unreachable_endpoint.lock().take()
+    }
+
+    /// Sets the filter of the incoming packets and returns the old one.
+    ///
+    /// The packets that have already been received are not affected.
+    pub fn set_filter(
+        &self,
+        filter: Option<Arc<dyn PacketFilter>>,
+    ) -> Option<Arc<dyn PacketFilter>> {
+        core::mem::replace(&mut *self.0.inner.filter.lock(), filter)
+    }
+
+    /// Returns whether the ICMP errors are queued (i.e., `IP_RECVERR`).
+    pub fn is_recv_err(&self) -> bool {
+        self.0.inner.is_recv_err.load(Ordering::Relaxed)
+    }
+
+    /// Sets whether the ICMP errors are queued (i.e., `IP_RECVERR`).
+    ///
+    /// The queued errors are discarded if the option is disabled.
+    pub fn set_recv_err(&self, is_recv_err: bool) {
+        let mut error_queue = self.0.inner.error_queue.lock();
+        self.0
+            .inner
+            .is_recv_err
+            .store(is_recv_err, Ordering::Relaxed);
+        if !is_recv_err {
+            error_queue.clear();
+        }
+    }
+
+    /// Returns whether there are queued ICMP errors.
+    pub fn has_queued_error(&self) -> bool {
+        !self.0.inner.error_queue.lock().is_empty()
+    }
+
+    /// Takes the oldest queued ICMP error, if any.
+    ///
+    /// Like Linux, the unreachable endpoint is updated to the remote endpoint of the next queued
+    /// error, or cleared if there are no more queued errors.
+    pub fn take_queued_error(&self) -> Option<UdpIcmpError> {
+        let mut error_queue = self.0.inner.error_queue.lock();
+        let error = error_queue.pop_front()?;
+        *self.0.inner.unreachable_endpoint.lock() = error_queue
+            .front()
+            .map(|next_error| next_error.remote_endpoint);
+        Some(error)
+    }
+
     /// Calls `f` with an immutable reference to the associated [`RawUdpSocket`].
     //
     // NOTE: If a mutable reference is required, add a method above that correctly updates the next
@@ -19,5 +19,9 @@ bitflags::bitflags! {
         const CLOSED_RECV = 4;
         /// Sending data isn't possible anymore.
         const CLOSED_SEND = 8;
+        /// An asynchronous error (e.g., an ICMP port unreachable message) is reported.
+        const ERROR = 16;
+        /// New urgent data (i.e., TCP out-of-band data) has arrived.
+        const URGENT = 32;
     }
 }
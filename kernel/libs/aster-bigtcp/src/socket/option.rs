@@ -11,28 +11,44 @@ pub trait RawTcpSetOption {
     /// Polling the iface _may_ be required after this method succeeds.
     fn set_keep_alive(&self, interval: Option<Duration>) -> NeedIfacePoll;
 
+    /// Sets the timeout after which the connection is aborted if nothing is received.
+    ///
+    /// Polling the iface _may_ be required after this method succeeds.
+    fn set_timeout(&self, timeout: Option<Duration>) -> NeedIfacePoll;
+
     /// Enables or disables Nagle’s Algorithm.
     ///
     /// Polling the iface is _not_ required after this method succeeds.
     fn set_nagle_enabled(&self, enabled: bool);
+
+    /// Sets whether the urgent data is received inline.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    fn set_urgent_inline(&self, is_inline: bool);
 }
 
 /// Socket options on a raw socket.
 pub struct RawTcpOption {
     /// The keep alive interval.
     pub keep_alive: Option<Duration>,
+    /// The timeout after which the connection is aborted if nothing is received.
+    pub timeout: Option<Duration>,
     /// Whether Nagle's algorithm is enabled.
     pub is_nagle_enabled: bool,
+    /// Whether the urgent data is received inline.
+    pub is_urgent_inline: bool,
 }
 
 impl RawTcpOption {
     pub(super) fn apply(&self, socket: &mut RawTcpSocket) {
         socket.set_keep_alive(self.keep_alive);
+        socket.set_timeout(self.timeout);
         socket.set_nagle_enabled(self.is_nagle_enabled);
     }
 
     pub(super) fn inherit(from: &RawTcpSocket, to: &mut RawTcpSocket) {
         to.set_keep_alive(from.keep_alive());
+        to.set_timeout(from.timeout());
         to.set_nagle_enabled(from.nagle_enabled());
     }
 }
@@ -1,5 +1,10 @@
 // SPDX-License-Identifier: MPL-2.0
 
-pub use smoltcp::wire::{EthernetAddress, IpAddress, IpCidr, IpEndpoint, Ipv4Address, Ipv4Cidr};
+mod header;
+
+pub use header::{Ipv4Header, TcpHeader, UdpHeader};
+pub use smoltcp::wire::{
+    EthernetAddress, EthernetFrame, IpAddress, IpCidr, IpEndpoint, Ipv4Address, Ipv4Cidr,
+};
 
 pub type PortNum = u16;
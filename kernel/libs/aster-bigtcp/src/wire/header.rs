@@ -0,0 +1,176 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Checked views of the protocol headers.
+//!
+//! The packet views in [`smoltcp::wire`] (e.g., [`Ipv4Packet::new_checked`]) check that the
+//! buffer contains the whole packet, as indicated by the length fields in the headers. However,
+//! some buffers contain only the headers and a truncated payload. A typical example is the
+//! original datagram carried in an ICMP error message, which is truncated to fit in the minimum
+//! MTU. The views here check that the buffer contains the whole header, so the header fields can
+//! be read safely without manual offset arithmetic, but the payload may be truncated.
+//!
+//! All multi-byte fields are read from the byte slices in the network byte order, so the buffers
+//! do not need to be aligned.
+//!
+//! For Ethernet, [`EthernetFrame::new_checked`] already checks only the header, since the frame
+//! has no length field. So it can be used directly.
+//!
+//! [`EthernetFrame::new_checked`]: smoltcp::wire::EthernetFrame::new_checked
+
+use smoltcp::wire::{
+    Error, IpProtocol, Ipv4Address, Ipv4Packet, TcpPacket, TcpSeqNumber, UdpPacket,
+    IPV4_HEADER_LEN, TCP_HEADER_LEN, UDP_HEADER_LEN,
+};
+
+/// A checked view of an IPv4 header, followed by a possibly truncated payload.
+#[derive(Clone, Copy, Debug)]
+pub struct Ipv4Header<'a> {
+    data: &'a [u8],
+    header_len: usize,
+}
+
+impl<'a> Ipv4Header<'a> {
+    /// Creates a view of the IPv4 header at the beginning of the buffer.
+    ///
+    /// This method fails if the version is not 4, or if the buffer is too short to contain the
+    /// header (including the options).
+    pub fn new_checked(data: &'a [u8]) -> Result<Self, Error> {
+        if data.len() < IPV4_HEADER_LEN {
+            return Err(Error);
+        }
+
+        let packet = Ipv4Packet::new_unchecked(data);
+        let header_len = packet.header_len() as usize;
+        if packet.version() != 4 || header_len < IPV4_HEADER_LEN || data.len() < header_len {
+            return Err(Error);
+        }
+
+        Ok(Self { data, header_len })
+    }
+
+    /// Returns the length of the header, including the options.
+    pub fn header_len(&self) -> usize {
+        self.header_len
+    }
+
+    /// Returns the protocol of the payload.
+    pub fn next_header(&self) -> IpProtocol {
+        self.packet().next_header()
+    }
+
+    /// Returns the source address.
+    pub fn src_addr(&self) -> Ipv4Address {
+        self.packet().src_addr()
+    }
+
+    /// Returns the destination address.
+    pub fn dst_addr(&self) -> Ipv4Address {
+        self.packet().dst_addr()
+    }
+
+    /// Returns the payload, which is truncated if the buffer is shorter than the total length.
+    pub fn payload(&self) -> &'a [u8] {
+        let total_len = self.packet().total_len() as usize;
+        let end = total_len.clamp(self.header_len, self.data.len());
+        &self.data[self.header_len..end]
+    }
+
+    fn packet(&self) -> Ipv4Packet<&'a [u8]> {
+        Ipv4Packet::new_unchecked(self.data)
+    }
+}
+
+/// A checked view of a TCP header, followed by a possibly truncated payload.
+#[derive(Clone, Copy, Debug)]
+pub struct TcpHeader<'a> {
+    data: &'a [u8],
+    header_len: usize,
+}
+
+impl<'a> TcpHeader<'a> {
+    /// Creates a view of the TCP header at the beginning of the buffer.
+    ///
+    /// This method fails if the buffer is too short to contain the header (including the
+    /// options).
+    pub fn new_checked(data: &'a [u8]) -> Result<Self, Error> {
+        if data.len() < TCP_HEADER_LEN {
+            return Err(Error);
+        }
+
+        let header_len = TcpPacket::new_unchecked(data).header_len() as usize;
+        if header_len < TCP_HEADER_LEN || data.len() < header_len {
+            return Err(Error);
+        }
+
+        Ok(Self { data, header_len })
+    }
+
+    /// Returns the length of the header, including the options.
+    pub fn header_len(&self) -> usize {
+        self.header_len
+    }
+
+    /// Returns the source port.
+    pub fn src_port(&self) -> u16 {
+        self.packet().src_port()
+    }
+
+    /// Returns the destination port.
+    pub fn dst_port(&self) -> u16 {
+        self.packet().dst_port()
+    }
+
+    /// Returns the sequence number.
+    pub fn seq_number(&self) -> TcpSeqNumber {
+        self.packet().seq_number()
+    }
+
+    /// Returns the payload, which may be truncated.
+    pub fn payload(&self) -> &'a [u8] {
+        &self.data[self.header_len..]
+    }
+
+    fn packet(&self) -> TcpPacket<&'a [u8]> {
+        TcpPacket::new_unchecked(self.data)
+    }
+}
+
+/// A checked view of a UDP header, followed by a possibly truncated payload.
+#[derive(Clone, Copy, Debug)]
+pub struct UdpHeader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> UdpHeader<'a> {
+    /// Creates a view of the UDP header at the beginning of the buffer.
+    ///
+    /// This method fails if the buffer is too short to contain the header.
+    pub fn new_checked(data: &'a [u8]) -> Result<Self, Error> {
+        if data.len() < UDP_HEADER_LEN {
+            return Err(Error);
+        }
+
+        Ok(Self { data })
+    }
+
+    /// Returns the source port.
+    pub fn src_port(&self) -> u16 {
+        self.packet().src_port()
+    }
+
+    /// Returns the destination port.
+    pub fn dst_port(&self) -> u16 {
+        self.packet().dst_port()
+    }
+
+    /// Returns the payload, which is truncated if the buffer is shorter than the length field.
+    pub fn payload(&self) -> &'a [u8] {
+        let len = self.packet().len() as usize;
+        let end = len.clamp(UDP_HEADER_LEN, self.data.len());
+        &self.data[UDP_HEADER_LEN..end]
+    }
+
+    fn packet(&self) -> UdpPacket<&'a [u8]> {
+        UdpPacket::new_unchecked(self.data)
+    }
+}
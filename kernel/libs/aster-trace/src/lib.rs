@@ -0,0 +1,110 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Static tracepoints for Asterinas.
+//!
+//! A tracepoint is a named event at a fixed place in the kernel, e.g., where a system call is
+//! entered. Tracepoints are declared with [`declare_tracepoint!`] and emitted with
+//! [`tracepoint!`]. They are disabled by default and can be enabled at runtime, by name after
+//! being registered with [`register_tracepoints`]. A disabled tracepoint costs no more than
+//! loading a flag, since the arguments to [`tracepoint!`] are not even evaluated.
+//!
+//! An enabled tracepoint records an event with a timestamp and a few integer arguments in the
+//! ring buffer of the current CPU. Recording never allocates memory or waits for other CPUs, so
+//! it is safe in the interrupt context. When a ring buffer is full, the oldest events in it are
+//! overwritten. The events of all CPUs can be read with [`read_events`], in the order of their
+//! timestamps.
+//!
+//! Besides, [`TraceProbe`]s can be attached to a tracepoint, e.g., to run BPF programs. They are
+//! called with the arguments of each event, even if the tracepoint is not enabled.
+//!
+//! ```no_run
+//! declare_tracepoint! {
+//!     /// Emitted when a block I/O request is submitted.
+//!     pub static BIO_SUBMIT: block:bio_submit(sector, nr_sectors);
+//! }
+//!
+//! register_tracepoints(&[&BIO_SUBMIT]);
+//! set_tracepoints_enabled("block:bio_submit", true);
+//! tracepoint!(BIO_SUBMIT, sid.to_raw(), nr_sectors);
+//! ```
+#![no_std]
+#![deny(unsafe_code)]
+
+extern crate alloc;
+
+mod ring;
+mod tracepoint;
+
+pub use self::{
+    ring::{
+        clear_events, is_tracing_on, num_lost_events, read_events, set_tracing_on, TraceEvent,
+        RING_LEN,
+    },
+    tracepoint::{
+        all_tracepoints, register_tracepoints, set_tracepoints_enabled, ArgFormat, IntoTraceArg,
+        TraceArg, TraceProbe, Tracepoint, MAX_TRACE_ARGS,
+    },
+};
+
+/// Declares static tracepoints.
+///
+/// Each tracepoint is declared with its subsystem, its name, and the names of its arguments.
+/// An argument is shown as an unsigned decimal by default, or it can be followed by `: signed`
+/// or `: hex`.
+///
+/// ```no_run
+/// declare_tracepoint! {
+///     /// Emitted when a system call returns.
+///     pub static SYS_EXIT: raw_syscalls:sys_exit(tid, nr, ret: signed);
+/// }
+/// ```
+#[macro_export]
+macro_rules! declare_tracepoint {
+    ($(
+        $(#[$attr:meta])*
+        $vis:vis static $tp:ident: $subsystem:ident:$name:ident(
+            $($arg:ident $(: $format:ident)?),* $(,)?
+        );
+    )*) => {
+        $(
+            $(#[$attr])*
+            $vis static $tp: $crate::Tracepoint = $crate::Tracepoint::new(
+                stringify!($subsystem),
+                stringify!($name),
+                &[$(
+                    $crate::TraceArg::new(
+                        stringify!($arg),
+                        $crate::__trace_arg_format!($($format)?),
+                    )
+                ),*],
+            );
+        )*
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __trace_arg_format {
+    () => {
+        $crate::ArgFormat::Unsigned
+    };
+    (signed) => {
+        $crate::ArgFormat::Signed
+    };
+    (hex) => {
+        $crate::ArgFormat::Hex
+    };
+}
+
+/// Emits an event of a tracepoint if the tracepoint is enabled or has probes attached.
+///
+/// The arguments are only evaluated if the tracepoint is enabled or probed. They can be of any integer
+/// type or `bool`, and there should be as many of them as the declared arguments.
+#[macro_export]
+macro_rules! tracepoint {
+    ($tp:path $(, $arg:expr)* $(,)?) => {
+        if $tp.is_active() {
+            $tp.emit(&[$($crate::IntoTraceArg::into_trace_arg($arg)),*]);
+        }
+    };
+}
@@ -0,0 +1,287 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The per-CPU ring buffers of the trace events.
+
+use alloc::vec::Vec;
+use core::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use ostd::{
+    arch::{read_tsc, tsc_freq},
+    cpu::{all_cpus, CpuId},
+    cpu_local,
+    sync::{LocalIrqDisabled, SpinLock},
+    trap,
+};
+use spin::Once;
+
+use crate::{ArgFormat, Tracepoint, MAX_TRACE_ARGS};
+
+/// The number of the events that the ring buffer of each CPU holds.
+pub const RING_LEN: usize = 4096;
+
+/// Whether the enabled tracepoints record events.
+static IS_TRACING_ON: AtomicBool = AtomicBool::new(true);
+
+/// Whether the ring buffers have been allocated.
+static RINGS_ALLOCATED: Once = Once::new();
+
+cpu_local! {
+    static RING: SpinLock<TraceRing, LocalIrqDisabled> = SpinLock::new(TraceRing::new());
+}
+
+/// An event recorded by a tracepoint.
+#[derive(Debug, Clone, Copy)]
+pub struct TraceEvent {
+    /// The CPU that records the event.
+    pub cpu: CpuId,
+    /// The time when the event is recorded, since the system boots.
+    pub timestamp: Duration,
+    /// The tracepoint.
+    pub tracepoint: &'static Tracepoint,
+    args: [u64; MAX_TRACE_ARGS],
+}
+
+impl TraceEvent {
+    /// Returns the arguments, one for each declared argument of the tracepoint.
+    pub fn args(&self) -> &[u64] {
+        &self.args[..self.tracepoint.args().len()]
+    }
+}
+
+/// Shows the event like a line of the `trace` file in Linux, e.g.,
+/// `[001]     12.345678: sched_switch: prev_tid=1 next_tid=2`.
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{:03}] {:>6}.{:06}: {}:",
+            self.cpu.as_usize(),
+            self.timestamp.as_secs(),
+            self.timestamp.subsec_micros(),
+            self.tracepoint.name()
+        )?;
+        for (arg, value) in self.tracepoint.args().iter().zip(self.args()) {
+            match arg.format() {
+                ArgFormat::Unsigned => write!(f, " {}={}", arg.name(), value)?,
+                ArgFormat::Signed => write!(f, " {}={}", arg.name(), *value as i64)?,
+                ArgFormat::Hex => write!(f, " {}={:#x}", arg.name(), value)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether the enabled tracepoints record events.
+pub fn is_tracing_on() -> bool {
+    IS_TRACING_ON.load(Ordering::Relaxed)
+}
+
+/// Starts or stops recording events, without changing which tracepoints are enabled.
+///
+/// This is like writing to `tracing_on` in Linux. Tracing is on by default.
+pub fn set_tracing_on(is_on: bool) {
+    IS_TRACING_ON.store(is_on, Ordering::Relaxed);
+}
+
+/// Reads the events in the ring buffers of all CPUs, in the order of their timestamps.
+///
+/// The events are not removed from the ring buffers.
+pub fn read_events() -> Vec<TraceEvent> {
+    let mut events = Vec::new();
+    for cpu in all_cpus() {
+        // Reserve the space first, so that no memory is allocated while the IRQs are disabled.
+        events.reserve(RING_LEN);
+        let ring = RING.get_on_cpu(cpu).lock();
+        events.extend(ring.iter().map(|entry| TraceEvent {
+            cpu,
+            timestamp: tsc_to_duration(entry.tsc),
+            tracepoint: entry.tracepoint,
+            args: entry.args,
+        }));
+    }
+
+    // The events of each CPU are already sorted, which the stable sorting takes advantage of.
+    events.sort_by_key(|event| event.timestamp);
+    events
+}
+
+/// Returns the number of the events that have been overwritten since the last clearing.
+pub fn num_lost_events() -> u64 {
+    all_cpus()
+        .map(|cpu| RING.get_on_cpu(cpu).lock().num_lost)
+        .sum()
+}
+
+/// Clears the events in the ring buffers of all CPUs.
+pub fn clear_events() {
+    for cpu in all_cpus() {
+        RING.get_on_cpu(cpu).lock().clear();
+    }
+}
+
+/// Allocates the ring buffers of all CPUs if they have not been allocated.
+pub(crate) fn alloc_rings() {
+    RINGS_ALLOCATED.call_once(|| {
+        for cpu in all_cpus() {
+            let entries = Vec::with_capacity(RING_LEN);
+            RING.get_on_cpu(cpu).lock().entries = entries;
+        }
+    });
+}
+
+/// Records an event in the ring buffer of the current CPU.
+pub(crate) fn record_event(tracepoint: &'static Tracepoint, args: &[u64]) {
+    if !is_tracing_on() {
+        return;
+    }
+
+    let mut entry = TraceEntry {
+        tsc: 0,
+        tracepoint,
+        args: [0; MAX_TRACE_ARGS],
+    };
+    let len = args.len().min(MAX_TRACE_ARGS);
+    entry.args[..len].copy_from_slice(&args[..len]);
+
+    let irq_guard = trap::disable_local();
+    let mut ring = RING.get_with(&irq_guard).lock();
+    // Read the TSC with the lock held, so that the events of a CPU are in order.
+    entry.tsc = read_tsc();
+    ring.push(entry);
+}
+
+fn tsc_to_duration(tsc: u64) -> Duration {
+    let freq = tsc_freq();
+    if freq == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_nanos((tsc as u128 * 1_000_000_000 / freq as u128) as u64)
+}
+
+/// An event in a ring buffer.
+#[derive(Clone, Copy)]
+struct TraceEntry {
+    tsc: u64,
+    tracepoint: &'static Tracepoint,
+    args: [u64; MAX_TRACE_ARGS],
+}
+
+/// A ring buffer of at most `N` events.
+struct TraceRing<const N: usize = RING_LEN> {
+    /// The events, which have no space until the ring buffer is allocated.
+    entries: Vec<TraceEntry>,
+    /// The index of the oldest event when the ring buffer is full.
+    head: usize,
+    /// The number of the overwritten events.
+    num_lost: u64,
+}
+
+impl<const N: usize> TraceRing<N> {
+    const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            head: 0,
+            num_lost: 0,
+        }
+    }
+
+    fn push(&mut self, entry: TraceEntry) {
+        // Never allocate memory here, since events may be recorded in the interrupt context.
+        if self.entries.capacity() == 0 {
+            return;
+        }
+
+        if self.entries.len() < N {
+            self.entries.push(entry);
+        } else {
+            self.entries[self.head] = entry;
+            self.head = (self.head + 1) % N;
+            self.num_lost += 1;
+        }
+    }
+
+    /// Iterates over the events from the oldest one.
+    fn iter(&self) -> impl Iterator<Item = &TraceEntry> {
+        let (newer, older) = self.entries.split_at(self.head);
+        older.iter().chain(newer)
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.head = 0;
+        self.num_lost = 0;
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use alloc::format;
+
+    use ostd::prelude::*;
+
+    use super::*;
+
+    crate::declare_tracepoint! {
+        static TEST: test_ring:test(value, delta: signed, addr: hex);
+    }
+
+    const TEST_RING_LEN: usize = 4;
+
+    fn entry(value: u64) -> TraceEntry {
+        TraceEntry {
+            tsc: value,
+            tracepoint: &TEST,
+            args: [value, (-1i64) as u64, 0x1000, 0, 0, 0, 0, 0],
+        }
+    }
+
+    fn values(ring: &TraceRing<TEST_RING_LEN>) -> Vec<u64> {
+        ring.iter().map(|entry| entry.args[0]).collect()
+    }
+
+    #[ktest]
+    fn push_and_overwrite() {
+        let mut ring = TraceRing::<TEST_RING_LEN>::new();
+
+        // Nothing is recorded before the ring buffer is allocated.
+        ring.push(entry(0));
+        assert!(values(&ring).is_empty());
+
+        ring.entries = Vec::with_capacity(TEST_RING_LEN);
+        for value in 1..=3 {
+            ring.push(entry(value));
+        }
+        assert_eq!(values(&ring), [1, 2, 3]);
+        assert_eq!(ring.num_lost, 0);
+
+        // The oldest events are overwritten.
+        for value in 4..=10 {
+            ring.push(entry(value));
+        }
+        assert_eq!(values(&ring), [7, 8, 9, 10]);
+        assert_eq!(ring.num_lost, 6);
+
+        ring.clear();
+        assert!(values(&ring).is_empty());
+        ring.push(entry(11));
+        assert_eq!(values(&ring), [11]);
+    }
+
+    #[ktest]
+    fn display_event() {
+        let event = TraceEvent {
+            cpu: CpuId::bsp(),
+            timestamp: Duration::from_micros(12_345_678),
+            tracepoint: &TEST,
+            args: entry(42).args,
+        };
+        assert_eq!(
+            format!("{}", event),
+            "[000]     12.345678: test: value=42 delta=-1 addr=0x1000"
+        );
+    }
+}
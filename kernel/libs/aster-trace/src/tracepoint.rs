@@ -0,0 +1,406 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{boxed::Box, sync::Arc, vec, vec::Vec};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use ostd::sync::{RcuOption, SpinLock};
+
+use crate::ring;
+
+/// The maximum number of the arguments of a tracepoint.
+pub const MAX_TRACE_ARGS: usize = 8;
+
+/// The registered tracepoints, which can be found by name.
+static TRACEPOINTS: SpinLock<Vec<&'static Tracepoint>> = SpinLock::new(Vec::new());
+
+/// A static tracepoint.
+///
+/// A tracepoint should be declared with [`declare_tracepoint!`] and emitted with
+/// [`tracepoint!`].
+///
+/// [`declare_tracepoint!`]: crate::declare_tracepoint
+/// [`tracepoint!`]: crate::tracepoint
+pub struct Tracepoint {
+    subsystem: &'static str,
+    name: &'static str,
+    args: &'static [TraceArg],
+    /// The bits of [`STATE_ENABLED`] and [`STATE_PROBED`], and a generation counter in units of
+    /// [`STATE_PROBE_GEN`].
+    state: AtomicU32,
+    #[allow(clippy::type_complexity)]
+    #[allow(clippy::box_collection)]
+    probes: RcuOption<Box<Vec<Arc<dyn TraceProbe>>>>,
+}
+
+/// The tracepoint records events in the ring buffers.
+const STATE_ENABLED: u32 = 1 << 0;
+/// The tracepoint has probes attached.
+const STATE_PROBED: u32 = 1 << 1;
+/// The unit of the generation counter, which is increased whenever a probe is attached.
+const STATE_PROBE_GEN: u32 = 1 << 2;
+
+/// A probe that is called with the arguments of the events of a tracepoint.
+///
+/// Probes are called whether the tracepoint is enabled or not, possibly in the interrupt
+/// context. So they must not sleep.
+pub trait TraceProbe: Send + Sync {
+    /// Handles an event with the arguments.
+    fn on_event(&self, args: &[u64]);
+}
+
+impl core::fmt::Debug for Tracepoint {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Tracepoint")
+            .field("subsystem", &self.subsystem)
+            .field("name", &self.name)
+            .field("args", &self.args)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Tracepoint {
+    /// Creates a disabled tracepoint.
+    ///
+    /// # Panics
+    ///
+    /// This method panics, at compile time for a static tracepoint, if the tracepoint has more
+    /// than [`MAX_TRACE_ARGS`] arguments.
+    #[doc(hidden)]
+    pub const fn new(
+        subsystem: &'static str,
+        name: &'static str,
+        args: &'static [TraceArg],
+    ) -> Self {
+        assert!(
+            args.len() <= MAX_TRACE_ARGS,
+            "too many arguments of the tracepoint"
+        );
+
+        Self {
+            subsystem,
+            name,
+            args,
+            state: AtomicU32::new(0),
+            probes: RcuOption::new_none(),
+        }
+    }
+
+    /// Returns the subsystem, e.g., `sched`.
+    pub fn subsystem(&self) -> &'static str {
+        self.subsystem
+    }
+
+    /// Returns the name, which is unique in the subsystem, e.g., `sched_switch`.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the declared arguments.
+    pub fn args(&self) -> &'static [TraceArg] {
+        self.args
+    }
+
+    /// Returns whether the tracepoint is enabled.
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.state.load(Ordering::Relaxed) & STATE_ENABLED != 0
+    }
+
+    /// Returns whether the tracepoint is enabled or has probes attached.
+    ///
+    /// Use [`tracepoint!`], which checks this first, to emit events.
+    ///
+    /// [`tracepoint!`]: crate::tracepoint
+    #[doc(hidden)]
+    #[inline(always)]
+    pub fn is_active(&self) -> bool {
+        self.state.load(Ordering::Relaxed) & (STATE_ENABLED | STATE_PROBED) != 0
+    }
+
+    /// Enables or disables the tracepoint.
+    ///
+    /// The ring buffers are allocated when a tracepoint is enabled for the first time.
+    pub fn set_enabled(&self, is_enabled: bool) {
+        if is_enabled {
+            ring::alloc_rings();
+            self.state.fetch_or(STATE_ENABLED, Ordering::Relaxed);
+        } else {
+            self.state.fetch_and(!STATE_ENABLED, Ordering::Relaxed);
+        }
+    }
+
+    /// Attaches a probe, which will be called on each event of the tracepoint.
+    pub fn attach_probe(&self, probe: Arc<dyn TraceProbe>) {
+        loop {
+            let probes = self.probes.read();
+            let new_probes = match probes.get() {
+                Some(probes_vec) => {
+                    let mut probes_cloned = probes_vec.clone();
+                    probes_cloned.push(probe.clone());
+                    probes_cloned
+                }
+                None => Box::new(vec![probe.clone()]),
+            };
+            if probes.compare_exchange(Some(new_probes)).is_ok() {
+                break;
+            }
+            // Contention on updating the probes, retry.
+            core::hint::spin_loop();
+        }
+
+        // Increasing the generation fails the concurrent `clear_probed`, which may have missed
+        // the new probe.
+        let _ = self
+            .state
+            .fetch_update(Ordering::Release, Ordering::Relaxed, |state| {
+                Some(state.wrapping_add(STATE_PROBE_GEN) | STATE_PROBED)
+            });
+    }
+
+    /// Detaches a probe that is previously attached.
+    ///
+    /// This method returns whether the probe is found. The probe may still be called for the
+    /// events that are being emitted on other CPUs after this method returns.
+    pub fn detach_probe(&self, probe: &Arc<dyn TraceProbe>) -> bool {
+        loop {
+            let probes = self.probes.read();
+            let Some(probes_vec) = probes.get() else {
+                return false;
+            };
+            let Some(index) = probes_vec
+                .iter()
+                .position(|attached| Arc::ptr_eq(attached, probe))
+            else {
+                return false;
+            };
+
+            let mut probes_cloned = probes_vec.clone();
+            probes_cloned.remove(index);
+            let is_empty = probes_cloned.is_empty();
+            let new_probes = (!is_empty).then_some(probes_cloned);
+            if probes.compare_exchange(new_probes).is_ok() {
+                if is_empty {
+                    self.clear_probed();
+                }
+                return true;
+            }
+            // Contention on updating the probes, retry.
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Clears [`STATE_PROBED`] unless a probe has been attached since the last one is detached.
+    fn clear_probed(&self) {
+        let mut state = self.state.load(Ordering::Acquire);
+        loop {
+            if self.probes.read().get().is_some() {
+                return;
+            }
+            match self.state.compare_exchange(
+                state,
+                state & !STATE_PROBED,
+                Ordering::Relaxed,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return,
+                Err(current) => state = current,
+            }
+        }
+    }
+
+    /// Records an event with the arguments and calls the attached probes.
+    ///
+    /// Use [`tracepoint!`] instead, which checks whether the tracepoint is active first.
+    ///
+    /// [`tracepoint!`]: crate::tracepoint
+    #[doc(hidden)]
+    pub fn emit(&'static self, args: &[u64]) {
+        debug_assert_eq!(args.len(), self.args.len());
+
+        let state = self.state.load(Ordering::Relaxed);
+        if state & STATE_ENABLED != 0 {
+            ring::record_event(self, args);
+        }
+        if state & STATE_PROBED != 0 {
+            let probes = self.probes.read();
+            if let Some(probes) = probes.get() {
+                for probe in probes.iter() {
+                    probe.on_event(args);
+                }
+            }
+        }
+    }
+}
+
+/// A declared argument of a tracepoint.
+#[derive(Debug, Clone, Copy)]
+pub struct TraceArg {
+    name: &'static str,
+    format: ArgFormat,
+}
+
+impl TraceArg {
+    #[doc(hidden)]
+    pub const fn new(name: &'static str, format: ArgFormat) -> Self {
+        Self { name, format }
+    }
+
+    /// Returns the name.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns how the values of the argument are shown.
+    pub fn format(&self) -> ArgFormat {
+        self.format
+    }
+}
+
+/// How the values of an argument of a tracepoint are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgFormat {
+    /// An unsigned decimal.
+    Unsigned,
+    /// A signed decimal, e.g., for the return value of a system call.
+    Signed,
+    /// A hexadecimal, e.g., for an address.
+    Hex,
+}
+
+/// A value that can be an argument of a tracepoint.
+pub trait IntoTraceArg {
+    /// Converts the value to the raw argument.
+    ///
+    /// A signed value is sign-extended, so that it can be shown with [`ArgFormat::Signed`].
+    fn into_trace_arg(self) -> u64;
+}
+
+macro_rules! impl_into_trace_arg {
+    ($($unsigned:ty),* ; $($signed:ty),*) => {
+        $(
+            impl IntoTraceArg for $unsigned {
+                fn into_trace_arg(self) -> u64 {
+                    self as u64
+                }
+            }
+        )*
+        $(
+            impl IntoTraceArg for $signed {
+                fn into_trace_arg(self) -> u64 {
+                    self as i64 as u64
+                }
+            }
+        )*
+    };
+}
+
+impl_into_trace_arg!(u8, u16, u32, usize, bool; i8, i16, i32, isize);
+
+impl IntoTraceArg for u64 {
+    fn into_trace_arg(self) -> u64 {
+        self
+    }
+}
+
+impl IntoTraceArg for i64 {
+    fn into_trace_arg(self) -> u64 {
+        self as u64
+    }
+}
+
+/// Registers the tracepoints, so that they can be listed and enabled by name.
+pub fn register_tracepoints(tracepoints: &[&'static Tracepoint]) {
+    TRACEPOINTS.lock().extend_from_slice(tracepoints);
+}
+
+/// Returns the registered tracepoints, sorted by their subsystems and names.
+pub fn all_tracepoints() -> Vec<&'static Tracepoint> {
+    let mut tracepoints = TRACEPOINTS.lock().clone();
+    tracepoints.sort_by_key(|tracepoint| (tracepoint.subsystem, tracepoint.name));
+    tracepoints
+}
+
+/// Enables or disables the registered tracepoints that match the pattern.
+///
+/// The pattern is `<subsystem>:<name>` or just `<name>`, like the events written to
+/// `set_event` in Linux. Either part can be `*` or empty to match any subsystem or name.
+///
+/// This function returns the number of the matched tracepoints.
+pub fn set_tracepoints_enabled(pattern: &str, is_enabled: bool) -> usize {
+    let (subsystem, name) = pattern.split_once(':').unwrap_or(("", pattern));
+    let matches =
+        |pattern: &str, value: &str| pattern.is_empty() || pattern == "*" || pattern == value;
+
+    let matched = TRACEPOINTS
+        .lock()
+        .iter()
+        .filter(|tracepoint| {
+            matches(subsystem, tracepoint.subsystem) && matches(name, tracepoint.name)
+        })
+        .copied()
+        .collect::<Vec<_>>();
+    for tracepoint in matched.iter() {
+        tracepoint.set_enabled(is_enabled);
+    }
+
+    matched.len()
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    crate::declare_tracepoint! {
+        static FOO: test_tracepoint:foo(a, b: signed);
+        static BAR: test_tracepoint:bar();
+    }
+
+    #[ktest]
+    fn enable_by_pattern() {
+        register_tracepoints(&[&FOO, &BAR]);
+        assert_eq!(FOO.args()[1].format(), ArgFormat::Signed);
+
+        assert_eq!(set_tracepoints_enabled("test_tracepoint:foo", true), 1);
+        assert!(FOO.is_enabled() && !BAR.is_enabled());
+        assert_eq!(set_tracepoints_enabled("test_tracepoint:*", true), 2);
+        assert!(FOO.is_enabled() && BAR.is_enabled());
+        assert_eq!(set_tracepoints_enabled("bar", false), 1);
+        assert!(FOO.is_enabled() && !BAR.is_enabled());
+        assert_eq!(set_tracepoints_enabled("test_tracepoint:", false), 2);
+        assert!(!FOO.is_enabled() && !BAR.is_enabled());
+
+        assert_eq!(set_tracepoints_enabled("test_tracepoint:baz", true), 0);
+        assert_eq!(set_tracepoints_enabled("other:foo", true), 0);
+    }
+
+    #[ktest]
+    fn attach_and_detach_probe() {
+        use core::sync::atomic::AtomicU64;
+
+        struct SumProbe(AtomicU64);
+
+        impl TraceProbe for SumProbe {
+            fn on_event(&self, args: &[u64]) {
+                self.0.fetch_add(args.iter().sum(), Ordering::Relaxed);
+            }
+        }
+
+        let sum_probe = Arc::new(SumProbe(AtomicU64::new(0)));
+        let probe: Arc<dyn TraceProbe> = sum_probe.clone();
+
+        // A probe is called even if the tracepoint is disabled.
+        FOO.attach_probe(probe.clone());
+        assert!(!FOO.is_enabled() && FOO.is_active());
+        crate::tracepoint!(FOO, 1u64, 2u64);
+        assert_eq!(sum_probe.0.load(Ordering::Relaxed), 3);
+
+        assert!(FOO.detach_probe(&probe));
+        assert!(!FOO.is_active());
+        assert!(!FOO.detach_probe(&probe));
+        crate::tracepoint!(FOO, 1u64, 2u64);
+        assert_eq!(sum_probe.0.load(Ordering::Relaxed), 3);
+    }
+}
@@ -0,0 +1,110 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use ostd::{cpu::context::UserContext, user::UserContextApi, Pod};
+
+/// The registers of a tracee, which are read and written by `PTRACE_GETREGSET` and
+/// `PTRACE_SETREGSET`.
+///
+/// This is the same as `struct user_regs_struct` in Linux.
+#[derive(Debug, Clone, Copy, Pod, Default)]
+#[repr(C)]
+pub struct UserRegs {
+    pub pc: usize,
+    pub ra: usize,
+    pub sp: usize,
+    pub gp: usize,
+    pub tp: usize,
+    pub t0: usize,
+    pub t1: usize,
+    pub t2: usize,
+    pub s0: usize,
+    pub s1: usize,
+    pub a0: usize,
+    pub a1: usize,
+    pub a2: usize,
+    pub a3: usize,
+    pub a4: usize,
+    pub a5: usize,
+    pub a6: usize,
+    pub a7: usize,
+    pub s2: usize,
+    pub s3: usize,
+    pub s4: usize,
+    pub s5: usize,
+    pub s6: usize,
+    pub s7: usize,
+    pub s8: usize,
+    pub s9: usize,
+    pub s10: usize,
+    pub s11: usize,
+    pub t3: usize,
+    pub t4: usize,
+    pub t5: usize,
+    pub t6: usize,
+}
+
+macro_rules! copy_regs {
+    ($src: expr, $dst: expr) => {
+        $dst.ra = $src.ra;
+        $dst.sp = $src.sp;
+        $dst.gp = $src.gp;
+        $dst.tp = $src.tp;
+        $dst.t0 = $src.t0;
+        $dst.t1 = $src.t1;
+        $dst.t2 = $src.t2;
+        $dst.s0 = $src.s0;
+        $dst.s1 = $src.s1;
+        $dst.a0 = $src.a0;
+        $dst.a1 = $src.a1;
+        $dst.a2 = $src.a2;
+        $dst.a3 = $src.a3;
+        $dst.a4 = $src.a4;
+        $dst.a5 = $src.a5;
+        $dst.a6 = $src.a6;
+        $dst.a7 = $src.a7;
+        $dst.s2 = $src.s2;
+        $dst.s3 = $src.s3;
+        $dst.s4 = $src.s4;
+        $dst.s5 = $src.s5;
+        $dst.s6 = $src.s6;
+        $dst.s7 = $src.s7;
+        $dst.s8 = $src.s8;
+        $dst.s9 = $src.s9;
+        $dst.s10 = $src.s10;
+        $dst.s11 = $src.s11;
+        $dst.t3 = $src.t3;
+        $dst.t4 = $src.t4;
+        $dst.t5 = $src.t5;
+        $dst.t6 = $src.t6;
+    };
+}
+
+impl UserRegs {
+    /// Creates the registers from the user context.
+    ///
+    /// The system call number is in `a7`, so `orig_syscall_num` is not needed.
+    pub fn from_context(user_ctx: &UserContext, _orig_syscall_num: Option<usize>) -> Self {
+        let mut regs = Self {
+            pc: user_ctx.instruction_pointer(),
+            ..Default::default()
+        };
+        copy_regs!(user_ctx.general_regs(), regs);
+        regs
+    }
+
+    /// Copies the registers to the user context.
+    ///
+    /// Changing `a7` at a system call entry changes the system call to be executed.
+    pub fn copy_to_context(
+        &self,
+        user_ctx: &mut UserContext,
+        orig_syscall_num: &mut Option<usize>,
+    ) {
+        user_ctx.set_instruction_pointer(self.pc);
+        copy_regs!(self, user_ctx.general_regs_mut());
+
+        if orig_syscall_num.is_some() {
+            *orig_syscall_num = Some(self.a7);
+        }
+    }
+}
@@ -2,7 +2,13 @@
 
 use ostd::cpu::context::{CpuExceptionInfo, UserContext};
 
-use crate::process::signal::{sig_num::SigNum, signals::fault::FaultSignal, SignalContext};
+use super::cpu::GpRegs;
+use crate::{
+    prelude::*,
+    process::signal::{
+        c_types::ucontext_t, sig_num::SigNum, signals::fault::FaultSignal, SignalContext,
+    },
+};
 
 impl SignalContext for UserContext {
     fn set_arguments(&mut self, sig_num: SigNum, siginfo_addr: usize, ucontext_addr: usize) {
@@ -17,3 +23,42 @@ impl From<&CpuExceptionInfo> for FaultSignal {
         unimplemented!()
     }
 }
+
+/// The CPU context saved in the signal frame.
+// FIXME: The layout should be the same as `struct sigcontext` in Linux.
+#[derive(Debug, Clone, Copy, Pod, Default)]
+#[repr(C)]
+pub struct SigContext {
+    pub gp_regs: GpRegs,
+    _reserved: [u64; 16],
+}
+
+impl SigContext {
+    /// Copies the registers from the user context.
+    pub fn copy_from_context(&mut self, user_ctx: &UserContext) {
+        self.gp_regs.copy_from_raw(user_ctx.general_regs());
+    }
+
+    /// Copies the registers to the user context.
+    pub fn copy_to_context(&self, user_ctx: &mut UserContext) {
+        self.gp_regs.copy_to_raw(user_ctx.general_regs_mut());
+    }
+}
+
+/// Saves the FPU state of the current task to the user stack.
+///
+/// Returns the new stack pointer.
+// FIXME: Implement FPU state on RISC-V platforms.
+pub fn save_fpu_state_to_user(
+    _user_ctx: &UserContext,
+    _ucontext: &mut ucontext_t,
+    stack_pointer: u64,
+) -> Result<u64> {
+    Ok(stack_pointer)
+}
+
+/// Restores the FPU state of the current task from the signal frame.
+// FIXME: Implement FPU state on RISC-V platforms.
+pub fn restore_fpu_state_from_user(_user_ctx: &UserContext, _ucontext: &ucontext_t) -> Result<()> {
+    Ok(())
+}
@@ -96,18 +96,22 @@ macro_rules! copy_gp_regs {
         $dst.r15 = $src.r15;
         $dst.rip = $src.rip;
         $dst.rflags = $src.rflags;
-        $dst.fsbase = $src.fsbase;
-        $dst.gsbase = $src.gsbase;
     };
 }
 
 impl GpRegs {
+    /// Copies the registers to the raw general registers.
+    ///
+    /// The FS base and the GS base are not copied. Like Linux, they are not restored from the
+    /// signal frame when returning from a signal handler.
     pub fn copy_to_raw(&self, dst: &mut RawGeneralRegs) {
         copy_gp_regs!(self, dst);
     }
 
     pub fn copy_from_raw(&mut self, src: &RawGeneralRegs) {
         copy_gp_regs!(src, self);
+        self.fsbase = src.fsbase;
+        self.gsbase = src.gsbase;
     }
 }
 
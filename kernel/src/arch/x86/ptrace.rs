@@ -0,0 +1,120 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use ostd::{cpu::context::UserContext, Pod};
+
+use super::signal::{USER_CS, USER_SS};
+
+/// The flags in `RFLAGS` that can be changed by the tracer.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/arch/x86/kernel/ptrace.c#L126>
+const USER_CHANGEABLE_FLAGS: usize = 0x54dd5;
+
+/// The registers of a tracee, which are read and written by `PTRACE_GETREGS` and `PTRACE_SETREGS`.
+///
+/// This is the same as `struct user_regs_struct` in Linux.
+#[derive(Debug, Clone, Copy, Pod, Default)]
+#[repr(C)]
+pub struct UserRegs {
+    pub r15: usize,
+    pub r14: usize,
+    pub r13: usize,
+    pub r12: usize,
+    pub rbp: usize,
+    pub rbx: usize,
+    pub r11: usize,
+    pub r10: usize,
+    pub r9: usize,
+    pub r8: usize,
+    pub rax: usize,
+    pub rcx: usize,
+    pub rdx: usize,
+    pub rsi: usize,
+    pub rdi: usize,
+    pub orig_rax: usize,
+    pub rip: usize,
+    pub cs: usize,
+    pub eflags: usize,
+    pub rsp: usize,
+    pub ss: usize,
+    pub fs_base: usize,
+    pub gs_base: usize,
+    pub ds: usize,
+    pub es: usize,
+    pub fs: usize,
+    pub gs: usize,
+}
+
+impl UserRegs {
+    /// Creates the registers from the user context.
+    ///
+    /// `orig_syscall_num` is the number of the system call that the tracee has entered, if the
+    /// tracee is stopped at a system call.
+    pub fn from_context(user_ctx: &UserContext, orig_syscall_num: Option<usize>) -> Self {
+        Self {
+            r15: user_ctx.r15(),
+            r14: user_ctx.r14(),
+            r13: user_ctx.r13(),
+            r12: user_ctx.r12(),
+            rbp: user_ctx.rbp(),
+            rbx: user_ctx.rbx(),
+            r11: user_ctx.r11(),
+            r10: user_ctx.r10(),
+            r9: user_ctx.r9(),
+            r8: user_ctx.r8(),
+            rax: user_ctx.rax(),
+            rcx: user_ctx.rcx(),
+            rdx: user_ctx.rdx(),
+            rsi: user_ctx.rsi(),
+            rdi: user_ctx.rdi(),
+            orig_rax: orig_syscall_num.unwrap_or(usize::MAX),
+            rip: user_ctx.rip(),
+            cs: USER_CS,
+            eflags: user_ctx.rflags(),
+            rsp: user_ctx.rsp(),
+            ss: USER_SS,
+            fs_base: user_ctx.fsbase(),
+            gs_base: user_ctx.gsbase(),
+            ds: 0,
+            es: 0,
+            fs: 0,
+            gs: 0,
+        }
+    }
+
+    /// Copies the registers to the user context.
+    ///
+    /// The segment selectors cannot be changed and are ignored. Setting `orig_rax` to `-1` at a
+    /// system call entry makes the tracee skip the system call.
+    pub fn copy_to_context(
+        &self,
+        user_ctx: &mut UserContext,
+        orig_syscall_num: &mut Option<usize>,
+    ) {
+        user_ctx.set_r15(self.r15);
+        user_ctx.set_r14(self.r14);
+        user_ctx.set_r13(self.r13);
+        user_ctx.set_r12(self.r12);
+        user_ctx.set_rbp(self.rbp);
+        user_ctx.set_rbx(self.rbx);
+        user_ctx.set_r11(self.r11);
+        user_ctx.set_r10(self.r10);
+        user_ctx.set_r9(self.r9);
+        user_ctx.set_r8(self.r8);
+        user_ctx.set_rax(self.rax);
+        user_ctx.set_rcx(self.rcx);
+        user_ctx.set_rdx(self.rdx);
+        user_ctx.set_rsi(self.rsi);
+        user_ctx.set_rdi(self.rdi);
+        user_ctx.set_rip(self.rip);
+        user_ctx.set_rflags(
+            (user_ctx.rflags() & !USER_CHANGEABLE_FLAGS) | (self.eflags & USER_CHANGEABLE_FLAGS),
+        );
+        user_ctx.set_rsp(self.rsp);
+        user_ctx.set_fsbase(self.fs_base);
+        user_ctx.set_gsbase(self.gs_base);
+
+        if orig_syscall_num.is_some() {
+            *orig_syscall_num = (self.orig_rax != usize::MAX).then_some(self.orig_rax);
+        }
+    }
+}
@@ -1,11 +1,22 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use ostd::cpu::context::{CpuException, CpuExceptionInfo, UserContext};
+use align_ext::AlignExt;
+use ostd::cpu::context::{CpuException, CpuExceptionInfo, FpuState, UserContext};
 
-use crate::process::signal::{
-    constants::*, sig_num::SigNum, signals::fault::FaultSignal, SignalContext,
+use crate::{
+    current_userspace,
+    prelude::*,
+    process::signal::{
+        c_types::ucontext_t, constants::*, sig_num::SigNum, signals::fault::FaultSignal,
+        SignalContext,
+    },
 };
 
+/// The user-space selector of the 64-bit code segment.
+pub(super) const USER_CS: usize = 0x33;
+/// The user-space selector of the data segment.
+pub(super) const USER_SS: usize = 0x2b;
+
 impl SignalContext for UserContext {
     fn set_arguments(&mut self, sig_num: SigNum, siginfo_addr: usize, ucontext_addr: usize) {
         self.set_rdi(sig_num.as_u8() as usize);
@@ -40,3 +51,218 @@ impl From<&CpuExceptionInfo> for FaultSignal {
         FaultSignal::new(num, code, addr)
     }
 }
+
+/// The CPU context saved in the signal frame.
+///
+/// This is the same as `struct sigcontext` in Linux.
+#[derive(Debug, Clone, Copy, Pod, Default)]
+#[repr(C)]
+pub struct SigContext {
+    pub r8: usize,
+    pub r9: usize,
+    pub r10: usize,
+    pub r11: usize,
+    pub r12: usize,
+    pub r13: usize,
+    pub r14: usize,
+    pub r15: usize,
+    pub rdi: usize,
+    pub rsi: usize,
+    pub rbp: usize,
+    pub rbx: usize,
+    pub rdx: usize,
+    pub rax: usize,
+    pub rcx: usize,
+    pub rsp: usize,
+    pub rip: usize,
+    pub eflags: usize,
+    pub cs: u16,
+    pub gs: u16,
+    pub fs: u16,
+    pub ss: u16,
+    pub err: usize,
+    pub trapno: usize,
+    pub oldmask: usize,
+    pub cr2: usize,
+    /// The address of the FPU state in the user space, or zero if the state is not saved.
+    pub fpstate: Vaddr,
+    _reserved: [u64; 8],
+}
+
+/// The flags in `RFLAGS` that can be changed by a signal handler.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/arch/x86/kernel/signal_64.c#L37>
+const FIX_EFLAGS: usize = 0x50dd5;
+
+impl SigContext {
+    /// Copies the registers from the user context.
+    pub fn copy_from_context(&mut self, user_ctx: &UserContext) {
+        self.r8 = user_ctx.r8();
+        self.r9 = user_ctx.r9();
+        self.r10 = user_ctx.r10();
+        self.r11 = user_ctx.r11();
+        self.r12 = user_ctx.r12();
+        self.r13 = user_ctx.r13();
+        self.r14 = user_ctx.r14();
+        self.r15 = user_ctx.r15();
+        self.rdi = user_ctx.rdi();
+        self.rsi = user_ctx.rsi();
+        self.rbp = user_ctx.rbp();
+        self.rbx = user_ctx.rbx();
+        self.rdx = user_ctx.rdx();
+        self.rax = user_ctx.rax();
+        self.rcx = user_ctx.rcx();
+        self.rsp = user_ctx.rsp();
+        self.rip = user_ctx.rip();
+        self.eflags = user_ctx.rflags();
+        self.cs = USER_CS as u16;
+        self.ss = USER_SS as u16;
+    }
+
+    /// Copies the registers to the user context.
+    ///
+    /// The segment selectors cannot be changed and are ignored. Like Linux, the FS base and the
+    /// GS base are not restored from the signal frame, and only the flags in [`FIX_EFLAGS`] are
+    /// restored.
+    pub fn copy_to_context(&self, user_ctx: &mut UserContext) {
+        user_ctx.set_r8(self.r8);
+        user_ctx.set_r9(self.r9);
+        user_ctx.set_r10(self.r10);
+        user_ctx.set_r11(self.r11);
+        user_ctx.set_r12(self.r12);
+        user_ctx.set_r13(self.r13);
+        user_ctx.set_r14(self.r14);
+        user_ctx.set_r15(self.r15);
+        user_ctx.set_rdi(self.rdi);
+        user_ctx.set_rsi(self.rsi);
+        user_ctx.set_rbp(self.rbp);
+        user_ctx.set_rbx(self.rbx);
+        user_ctx.set_rdx(self.rdx);
+        user_ctx.set_rax(self.rax);
+        user_ctx.set_rcx(self.rcx);
+        user_ctx.set_rsp(self.rsp);
+        user_ctx.set_rip(self.rip);
+        user_ctx.set_rflags((user_ctx.rflags() & !FIX_EFLAGS) | (self.eflags & FIX_EFLAGS));
+    }
+}
+
+/// The `uc_flags` bit indicating that the FPU state is saved in the XSAVE format.
+const UC_FP_XSTATE: u64 = 0x1;
+/// The `uc_flags` bit indicating that `ss` in the signal context is valid.
+const UC_SIGCONTEXT_SS: u64 = 0x2;
+/// The `uc_flags` bit indicating that `ss` is restored from the signal context.
+const UC_STRICT_RESTORE_SS: u64 = 0x4;
+
+/// The magic number in [`FpxSwBytes`] indicating that the extended state follows the legacy
+/// region.
+const FP_XSTATE_MAGIC1: u32 = 0x46505853;
+/// The magic number that follows the extended state in the signal frame.
+const FP_XSTATE_MAGIC2: u32 = 0x46505845;
+
+/// The size in bytes of the legacy region of the XSAVE area.
+const FXSAVE_AREA_SIZE: usize = 512;
+/// The offset of [`FpxSwBytes`] in the legacy region, which is reserved for the software.
+const FPX_SW_BYTES_OFFSET: usize = 464;
+
+/// The software-defined description of the extended state in the signal frame.
+///
+/// This is the same as `struct _fpx_sw_bytes` in Linux.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct FpxSwBytes {
+    magic1: u32,
+    /// The size of the FPU state, including [`FP_XSTATE_MAGIC2`].
+    extended_size: u32,
+    /// The state components in the XSAVE area.
+    xfeatures: u64,
+    /// The size of the XSAVE area, excluding [`FP_XSTATE_MAGIC2`].
+    xstate_size: u32,
+    padding: [u32; 7],
+}
+
+/// Saves the FPU state of the current task to the user stack.
+///
+/// The state is written in the XSAVE format (or the FXSAVE format if `XSAVE` is not supported)
+/// below `stack_pointer`, and its address is recorded in `ucontext`. Returns the new stack
+/// pointer, i.e., the address of the state.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/arch/x86/kernel/fpu/signal.c#L161>
+pub fn save_fpu_state_to_user(
+    user_ctx: &UserContext,
+    ucontext: &mut ucontext_t,
+    stack_pointer: u64,
+) -> Result<u64> {
+    let xstate_size = FpuState::standard_format_size();
+    let features = FpuState::standard_format_features();
+    let has_xstate = features != 0;
+
+    let frame_size = if has_xstate {
+        xstate_size + size_of::<u32>()
+    } else {
+        xstate_size
+    };
+    let mut frame = vec![0u8; frame_size];
+    user_ctx.fpu_state().save_standard_format(&mut frame);
+
+    if has_xstate {
+        let sw_bytes = FpxSwBytes {
+            magic1: FP_XSTATE_MAGIC1,
+            extended_size: frame_size as u32,
+            xfeatures: features,
+            xstate_size: xstate_size as u32,
+            padding: [0; 7],
+        };
+        frame[FPX_SW_BYTES_OFFSET..FPX_SW_BYTES_OFFSET + size_of::<FpxSwBytes>()]
+            .copy_from_slice(sw_bytes.as_bytes());
+        frame[xstate_size..].copy_from_slice(&FP_XSTATE_MAGIC2.to_ne_bytes());
+    }
+
+    // The XSAVE area must be 64-byte aligned.
+    let fpstate_addr = (stack_pointer - frame_size as u64).align_down(64);
+    current_userspace!().write_bytes(fpstate_addr as Vaddr, &mut VmReader::from(&frame[..]))?;
+
+    ucontext.uc_flags = UC_SIGCONTEXT_SS | UC_STRICT_RESTORE_SS;
+    if has_xstate {
+        ucontext.uc_flags |= UC_FP_XSTATE;
+    }
+    ucontext.uc_mcontext.fpstate = fpstate_addr as Vaddr;
+
+    Ok(fpstate_addr)
+}
+
+/// Restores the FPU state of the current task from the signal frame.
+///
+/// If the frame does not contain the FPU state, the state is reset to the initial one. If the
+/// extended state is not described correctly, only the legacy region is restored.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/arch/x86/kernel/fpu/signal.c#L268>
+pub fn restore_fpu_state_from_user(user_ctx: &UserContext, ucontext: &ucontext_t) -> Result<()> {
+    let fpstate_addr = ucontext.uc_mcontext.fpstate;
+    if fpstate_addr == 0 {
+        FpuState::init().restore();
+        return Ok(());
+    }
+
+    let user_space = current_userspace!();
+
+    let sw_bytes = user_space.read_val::<FpxSwBytes>(fpstate_addr + FPX_SW_BYTES_OFFSET)?;
+    let xstate_size = sw_bytes.xstate_size as usize;
+    let is_extended = FpuState::standard_format_features() != 0
+        && sw_bytes.magic1 == FP_XSTATE_MAGIC1
+        && (FXSAVE_AREA_SIZE..=FpuState::standard_format_size()).contains(&xstate_size)
+        && sw_bytes.extended_size as usize == xstate_size + size_of::<u32>()
+        && user_space.read_val::<u32>(fpstate_addr + xstate_size)? == FP_XSTATE_MAGIC2;
+
+    let len = if is_extended {
+        xstate_size
+    } else {
+        FXSAVE_AREA_SIZE
+    };
+    let mut frame = vec![0u8; len];
+    user_space.read_bytes(fpstate_addr, &mut VmWriter::from(&mut frame[..]))?;
+
+    user_ctx
+        .fpu_state()
+        .restore_standard_format(&frame)
+        .map_err(|_| Error::with_message(Errno::EFAULT, "the FPU state is invalid"))
+}
@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{
+    insn::BpfInsn,
+    map::{new_map, BpfMap, MapAttr, MapType, UpdateFlag},
+    prog::{BpfProg, ProgType, TracepointLink},
+};
+use crate::{
+    events::IoEvents,
+    fs::{
+        file_handle::FileLike,
+        file_table::FileDesc,
+        utils::{InodeMode, Metadata},
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+};
+
+/// A file that refers to a BPF map.
+pub struct BpfMapFile(Arc<dyn BpfMap>);
+
+impl BpfMapFile {
+    /// Creates a map with the attributes.
+    pub fn new(map_type: u32, key_size: u32, value_size: u32, max_entries: u32) -> Result<Self> {
+        let map_type = MapType::try_from(map_type)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the BPF map type is not supported"))?;
+        let map = new_map(MapAttr {
+            map_type,
+            key_size,
+            value_size,
+            max_entries,
+        })?;
+
+        Ok(Self(map))
+    }
+
+    /// Returns the size of the keys.
+    pub fn key_size(&self) -> usize {
+        self.0.attr().key_size as usize
+    }
+
+    /// Returns the size of the values.
+    pub fn value_size(&self) -> usize {
+        self.0.attr().value_size as usize
+    }
+
+    /// Looks up the value of the key and returns a copy of it.
+    pub fn lookup(&self, key: &[u8]) -> Result<Vec<u8>> {
+        match self.0.lookup(key) {
+            Some(value) => Ok(value.lock().to_vec()),
+            None => return_errno_with_message!(Errno::ENOENT, "the BPF map element does not exist"),
+        }
+    }
+
+    /// Creates or updates the element of the key as specified by the flags.
+    pub fn update(&self, key: &[u8], value: &[u8], flags: u64) -> Result<()> {
+        let flag = UpdateFlag::try_from(flags)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the update flags are invalid"))?;
+        self.0.update(key, value, flag)
+    }
+
+    /// Deletes the element of the key.
+    pub fn delete(&self, key: &[u8]) -> Result<()> {
+        self.0.delete(key)
+    }
+
+    /// Returns the key after the given key, or the first key if the given key is not found.
+    pub fn next_key(&self, key: Option<&[u8]>) -> Result<Vec<u8>> {
+        self.0.next_key(key)
+    }
+}
+
+/// A file that refers to a BPF program.
+pub struct BpfProgFile(Arc<BpfProg>);
+
+impl BpfProgFile {
+    /// Verifies and loads a program.
+    ///
+    /// The maps used by the program are looked up by `get_file` from their file descriptors.
+    pub fn new(
+        prog_type: u32,
+        insns: &[BpfInsn],
+        mut get_file: impl FnMut(FileDesc) -> Result<Arc<dyn FileLike>>,
+    ) -> Result<Self> {
+        let prog_type = ProgType::try_from(prog_type).map_err(|_| {
+            Error::with_message(Errno::EINVAL, "the BPF program type is not supported")
+        })?;
+        let prog = BpfProg::new(prog_type, insns, |fd| {
+            let file = get_file(fd)?;
+            let Some(map_file) = file.downcast_ref::<BpfMapFile>() else {
+                return_errno_with_message!(Errno::EINVAL, "the file is not a BPF map");
+            };
+            Ok(map_file.0.clone())
+        })?;
+
+        Ok(Self(Arc::new(prog)))
+    }
+
+    pub(super) fn prog(&self) -> &Arc<BpfProg> {
+        &self.0
+    }
+}
+
+/// A file that refers to the attachment of a BPF program.
+///
+/// The program is detached when the file is closed.
+pub struct BpfLinkFile(TracepointLink);
+
+impl BpfLinkFile {
+    /// Attaches the program to the tracepoint of the name.
+    pub fn new_raw_tracepoint(name: &str, prog_file: &BpfProgFile) -> Result<Self> {
+        let link = TracepointLink::attach(name, prog_file.0.clone())?;
+        Ok(Self(link))
+    }
+}
+
+macro_rules! impl_file_like_for_bpf_files {
+    ($($file:ty),*) => {
+        $(
+            impl Pollable for $file {
+                fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+                    let events = IoEvents::IN | IoEvents::OUT;
+                    events & mask
+                }
+            }
+
+            impl FileLike for $file {
+                fn metadata(&self) -> Metadata {
+                    // This is a dummy implementation.
+                    // TODO: Add "anonymous inode fs" and link the BPF files to it.
+                    Metadata::new_file(
+                        0,
+                        InodeMode::from_bits_truncate(0o600),
+                        aster_block::BLOCK_SIZE,
+                    )
+                }
+            }
+        )*
+    };
+}
+
+impl_file_like_for_bpf_files!(BpfMapFile, BpfProgFile, BpfLinkFile);
@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The helper functions that the eBPF programs can call.
+//!
+//! The arguments are passed in R1 to R5, and the return value is stored in R0.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man7/bpf-helpers.7.html>
+
+use aster_time::read_monotonic_time;
+use ostd::{cpu::current_cpu_racy, task::Task};
+
+use super::{
+    interp::Vm,
+    map::{BpfMap, UpdateFlag},
+    prog::ProgType,
+};
+use crate::{prelude::*, process::posix_thread::AsPosixThread, util::random::getrandom};
+
+/// A helper function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+#[repr(i32)]
+pub(super) enum Helper {
+    MapLookupElem = 1,
+    MapUpdateElem = 2,
+    MapDeleteElem = 3,
+    KtimeGetNs = 5,
+    GetPrandomU32 = 7,
+    GetSmpProcessorId = 8,
+    GetCurrentPidTgid = 14,
+    GetCurrentUidGid = 15,
+}
+
+impl Helper {
+    /// Returns the helper function of the ID if the program type can call it.
+    pub(super) fn new(id: i32, prog_type: ProgType) -> Result<Self> {
+        let helper = Self::try_from(id).map_err(|_| {
+            Error::with_message(Errno::EINVAL, "the BPF helper function is not supported")
+        })?;
+
+        // Like Linux, the information about the current task is only available for tracing.
+        if matches!(helper, Self::GetCurrentPidTgid | Self::GetCurrentUidGid)
+            && prog_type != ProgType::RawTracepoint
+        {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the BPF helper function is not allowed for the program type"
+            );
+        }
+
+        Ok(helper)
+    }
+
+    /// Calls the helper function, or returns `None` if the arguments are invalid pointers.
+    pub(super) fn call(self, vm: &mut Vm) -> Option<u64> {
+        let ret = match self {
+            Self::MapLookupElem => {
+                let map = vm.map(vm.reg(1))?;
+                let key = read_key(vm, map.as_ref(), 2)?;
+                match map.lookup(&key) {
+                    Some(value) => vm.map_value_pointer(value),
+                    None => 0,
+                }
+            }
+            Self::MapUpdateElem => {
+                let map = vm.map(vm.reg(1))?;
+                let key = read_key(vm, map.as_ref(), 2)?;
+                let mut value = vec![0; map.attr().value_size as usize];
+                vm.read(vm.reg(3), &mut value)?;
+                let res = UpdateFlag::try_from(vm.reg(4))
+                    .map_err(|_| Error::with_message(Errno::EINVAL, "the update flag is invalid"))
+                    .and_then(|flag| map.update(&key, &value, flag));
+                errno_of(res)
+            }
+            Self::MapDeleteElem => {
+                let map = vm.map(vm.reg(1))?;
+                let key = read_key(vm, map.as_ref(), 2)?;
+                errno_of(map.delete(&key))
+            }
+            Self::KtimeGetNs => read_monotonic_time().as_nanos() as u64,
+            Self::GetPrandomU32 => {
+                let mut bytes = [0u8; size_of::<u32>()];
+                getrandom(&mut bytes).unwrap();
+                u32::from_ne_bytes(bytes) as u64
+            }
+            Self::GetSmpProcessorId => current_cpu_racy().as_usize() as u64,
+            Self::GetCurrentPidTgid => {
+                // The current task may be a kernel task, e.g., in the interrupt context.
+                let Some((tid, pid)) = Task::current().and_then(|task| {
+                    let posix_thread = task.as_posix_thread()?;
+                    Some((posix_thread.tid(), posix_thread.process().pid()))
+                }) else {
+                    return Some(0);
+                };
+                ((pid as u64) << 32) | tid as u64
+            }
+            Self::GetCurrentUidGid => {
+                let Some((uid, gid)) = Task::current().and_then(|task| {
+                    let credentials = task.as_posix_thread()?.credentials();
+                    Some((credentials.ruid(), credentials.rgid()))
+                }) else {
+                    return Some(0);
+                };
+                ((u32::from(gid) as u64) << 32) | u32::from(uid) as u64
+            }
+        };
+
+        Some(ret)
+    }
+}
+
+/// Reads the key of the map at the address in the register.
+fn read_key(vm: &mut Vm, map: &dyn BpfMap, reg: usize) -> Option<Vec<u8>> {
+    let mut key = vec![0; map.attr().key_size as usize];
+    vm.read(vm.reg(reg), &mut key)?;
+    Some(key)
+}
+
+/// Converts the result to the return value of the helper functions, i.e., zero on success and
+/// the negative error number on failure.
+fn errno_of(res: Result<()>) -> u64 {
+    match res {
+        Ok(()) => 0,
+        Err(err) => (-(err.error() as i64)) as u64,
+    }
+}
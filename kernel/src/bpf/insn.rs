@@ -0,0 +1,508 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The instructions of the eBPF programs and the verifier.
+//!
+//! A program is verified and decoded when it is loaded. The verifier is a subset of that in
+//! Linux. It only accepts forward jumps, so a program always terminates, and it checks the
+//! instructions, the registers, the jump targets, and the helper functions statically. The
+//! memory accesses are checked by the interpreter at runtime instead.
+//!
+//! Reference: <https://www.kernel.org/doc/html/v6.13/bpf/standardization/instruction-set.html>
+
+use super::{helper::Helper, map::BpfMap, prog::ProgType};
+use crate::prelude::*;
+
+/// The maximum number of the instructions in a program.
+pub const BPF_MAXINSNS: usize = 4096;
+
+/// The number of the registers, including the read-only frame pointer.
+pub(super) const MAX_BPF_REG: usize = 11;
+
+/// The frame pointer, which points to the end of the stack.
+pub(super) const BPF_REG_FP: u8 = 10;
+
+// The classes of the instructions.
+const BPF_LD: u8 = 0x00;
+const BPF_LDX: u8 = 0x01;
+const BPF_ST: u8 = 0x02;
+const BPF_STX: u8 = 0x03;
+const BPF_ALU: u8 = 0x04;
+const BPF_JMP: u8 = 0x05;
+const BPF_JMP32: u8 = 0x06;
+const BPF_ALU64: u8 = 0x07;
+
+// Sizes of the load and store instructions.
+const BPF_W: u8 = 0x00;
+const BPF_H: u8 = 0x08;
+const BPF_B: u8 = 0x10;
+const BPF_DW: u8 = 0x18;
+
+// Modes of the load and store instructions.
+const BPF_IMM: u8 = 0x00;
+const BPF_ABS: u8 = 0x20;
+const BPF_IND: u8 = 0x40;
+const BPF_MEM: u8 = 0x60;
+const BPF_ATOMIC: u8 = 0xc0;
+
+// Operations of the ALU instructions.
+const BPF_ADD: u8 = 0x00;
+const BPF_SUB: u8 = 0x10;
+const BPF_MUL: u8 = 0x20;
+const BPF_DIV: u8 = 0x30;
+const BPF_OR: u8 = 0x40;
+const BPF_AND: u8 = 0x50;
+const BPF_LSH: u8 = 0x60;
+const BPF_RSH: u8 = 0x70;
+const BPF_NEG: u8 = 0x80;
+const BPF_MOD: u8 = 0x90;
+const BPF_XOR: u8 = 0xa0;
+const BPF_MOV: u8 = 0xb0;
+const BPF_ARSH: u8 = 0xc0;
+const BPF_END: u8 = 0xd0;
+
+// Operations of the jump instructions.
+const BPF_JA: u8 = 0x00;
+const BPF_JEQ: u8 = 0x10;
+const BPF_JGT: u8 = 0x20;
+const BPF_JGE: u8 = 0x30;
+const BPF_JSET: u8 = 0x40;
+const BPF_JNE: u8 = 0x50;
+const BPF_JSGT: u8 = 0x60;
+const BPF_JSGE: u8 = 0x70;
+const BPF_CALL: u8 = 0x80;
+const BPF_EXIT: u8 = 0x90;
+const BPF_JLT: u8 = 0xa0;
+const BPF_JLE: u8 = 0xb0;
+const BPF_JSLT: u8 = 0xc0;
+const BPF_JSLE: u8 = 0xd0;
+
+// The source of the ALU and jump instructions (`BPF_K` is zero).
+const BPF_X: u8 = 0x08;
+
+/// The flag of the atomic operations to fetch the old value.
+const BPF_FETCH: i32 = 0x01;
+
+/// The source register of a 64-bit immediate load that refers to a map by its file descriptor.
+const BPF_PSEUDO_MAP_FD: u8 = 1;
+
+/// An eBPF instruction in the user space (`struct bpf_insn` in Linux).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct BpfInsn {
+    code: u8,
+    /// The destination register in the lower 4 bits and the source register in the higher
+    /// 4 bits.
+    regs: u8,
+    off: i16,
+    imm: i32,
+}
+
+impl BpfInsn {
+    fn dst(&self) -> u8 {
+        self.regs & 0x0f
+    }
+
+    fn src(&self) -> u8 {
+        self.regs >> 4
+    }
+}
+
+/// A decoded instruction.
+///
+/// The jump offsets are relative to the next instruction, as in the encoded instructions.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Insn {
+    /// Computes the destination register with the operation and the source.
+    Alu {
+        is_64: bool,
+        op: AluOp,
+        dst: u8,
+        src: Src,
+    },
+    /// Negates the destination register.
+    Neg { is_64: bool, dst: u8 },
+    /// Converts the byte order of the lowest `width` bits of the destination register.
+    Endian { to_be: bool, width: u32, dst: u8 },
+    /// Loads a 64-bit immediate, which takes two instruction slots.
+    LoadImm64 { dst: u8, imm: u64 },
+    /// Loads a map handle, which takes two instruction slots.
+    LoadMap { dst: u8, map_index: u32 },
+    /// The second slot of a 64-bit immediate load, which is never executed.
+    Imm64Tail,
+    /// Loads big-endian data of the packet at `imm` (plus the source register, if any) into R0.
+    LoadPacket {
+        size: usize,
+        src: Option<u8>,
+        imm: i32,
+    },
+    /// Loads the memory at the source register plus the offset.
+    Load {
+        size: usize,
+        dst: u8,
+        src: u8,
+        off: i16,
+    },
+    /// Stores the source to the memory at the destination register plus the offset.
+    Store {
+        size: usize,
+        dst: u8,
+        src: Src,
+        off: i16,
+    },
+    /// Updates the memory at the destination register plus the offset atomically.
+    Atomic {
+        size: usize,
+        op: AluOp,
+        is_fetch: bool,
+        dst: u8,
+        src: u8,
+        off: i16,
+    },
+    /// Jumps forward unconditionally.
+    Jump(u16),
+    /// Jumps forward if the condition holds.
+    JumpIf {
+        is_64: bool,
+        cond: JumpCond,
+        dst: u8,
+        src: Src,
+        off: u16,
+    },
+    /// Calls the helper function.
+    Call(Helper),
+    /// Returns R0.
+    Exit,
+}
+
+/// A source of the ALU, store, and jump instructions.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Src {
+    /// The immediate, which is sign-extended to 64 bits.
+    K(i32),
+    X(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum AluOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Or,
+    And,
+    Xor,
+    Lsh,
+    Rsh,
+    Arsh,
+    Mov,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum JumpCond {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Sgt,
+    Sge,
+    Slt,
+    Sle,
+    Set,
+}
+
+/// Verifies and decodes a program.
+///
+/// The maps referred to by the program are resolved by `get_map` and appended to `maps`.
+pub(super) fn verify(
+    insns: &[BpfInsn],
+    prog_type: ProgType,
+    mut get_map: impl FnMut(i32) -> Result<Arc<dyn BpfMap>>,
+    maps: &mut Vec<Arc<dyn BpfMap>>,
+) -> Result<Box<[Insn]>> {
+    if insns.is_empty() || insns.len() > BPF_MAXINSNS {
+        return_errno_with_message!(Errno::E2BIG, "the BPF program is empty or too long");
+    }
+
+    let mut decoded = Vec::with_capacity(insns.len());
+    while decoded.len() < insns.len() {
+        let pc = decoded.len();
+        let insn = decode_insn(&insns[pc], prog_type)?;
+
+        // The second half of a 64-bit immediate load has only the immediate.
+        let insn = match insn {
+            Insn::LoadImm64 { dst, imm } => {
+                let Some(tail) = insns.get(pc + 1) else {
+                    return_errno_with_message!(Errno::EINVAL, "the BPF 64-bit load is incomplete");
+                };
+                if tail.code != 0 || tail.regs != 0 || tail.off != 0 {
+                    return_errno_with_message!(Errno::EINVAL, "the BPF 64-bit load is invalid");
+                }
+                let imm = imm | ((tail.imm as u32 as u64) << 32);
+
+                if insns[pc].src() == BPF_PSEUDO_MAP_FD {
+                    if tail.imm != 0 {
+                        return_errno_with_message!(Errno::EINVAL, "the BPF map load is invalid");
+                    }
+                    maps.push(get_map(imm as i32)?);
+                    Insn::LoadMap {
+                        dst,
+                        map_index: (maps.len() - 1) as u32,
+                    }
+                } else {
+                    Insn::LoadImm64 { dst, imm }
+                }
+            }
+            insn => insn,
+        };
+        let is_imm64 = matches!(insn, Insn::LoadImm64 { .. } | Insn::LoadMap { .. });
+
+        decoded.push(insn);
+        if is_imm64 {
+            decoded.push(Insn::Imm64Tail);
+        }
+    }
+
+    // Only forward jumps are allowed, so the program terminates at an exit instruction if the
+    // jumps never go beyond the program.
+    for (pc, insn) in decoded.iter().enumerate() {
+        let off = match insn {
+            Insn::Jump(off) | Insn::JumpIf { off, .. } => *off,
+            _ => continue,
+        };
+        let target = pc + 1 + off as usize;
+        if target >= decoded.len() || matches!(decoded[target], Insn::Imm64Tail) {
+            return_errno_with_message!(Errno::EINVAL, "the BPF jump is out of range");
+        }
+    }
+    if !matches!(decoded.last(), Some(Insn::Exit)) {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the BPF program does not end with an exit instruction"
+        );
+    }
+
+    Ok(decoded.into_boxed_slice())
+}
+
+/// Checks and decodes an instruction.
+///
+/// For a 64-bit immediate load, only the lower 32 bits of the immediate are decoded.
+fn decode_insn(insn: &BpfInsn, prog_type: ProgType) -> Result<Insn> {
+    let BpfInsn { code, off, imm, .. } = *insn;
+    let (dst, src) = (insn.dst(), insn.src());
+
+    if dst as usize >= MAX_BPF_REG || src as usize >= MAX_BPF_REG {
+        return_errno_with_message!(Errno::EINVAL, "the BPF register is invalid");
+    }
+    let check_dst_writable = || {
+        if dst == BPF_REG_FP {
+            return_errno_with_message!(Errno::EACCES, "the BPF frame pointer is read-only");
+        }
+        Ok(())
+    };
+    let size = || match code & 0x18 {
+        BPF_W => 4,
+        BPF_H => 2,
+        BPF_B => 1,
+        BPF_DW => 8,
+        _ => unreachable!(),
+    };
+    let alu_src = || {
+        if code & BPF_X != 0 {
+            Src::X(src)
+        } else {
+            Src::K(imm)
+        }
+    };
+
+    let insn = match code & 0x07 {
+        class @ (BPF_ALU | BPF_ALU64) => {
+            check_dst_writable()?;
+            let is_64 = class == BPF_ALU64;
+            // The signed division and the sign-extending moves are not supported.
+            if off != 0 {
+                return_errno_with_message!(Errno::EINVAL, "the BPF ALU offset is invalid");
+            }
+
+            let op = match code & 0xf0 {
+                BPF_NEG => return Ok(Insn::Neg { is_64, dst }),
+                BPF_END => {
+                    if is_64 || !matches!(imm, 16 | 32 | 64) {
+                        return_errno_with_message!(
+                            Errno::EINVAL,
+                            "the BPF byte swap is not supported"
+                        );
+                    }
+                    return Ok(Insn::Endian {
+                        to_be: code & BPF_X != 0,
+                        width: imm as u32,
+                        dst,
+                    });
+                }
+                BPF_ADD => AluOp::Add,
+                BPF_SUB => AluOp::Sub,
+                BPF_MUL => AluOp::Mul,
+                BPF_DIV => AluOp::Div,
+                BPF_MOD => AluOp::Mod,
+                BPF_OR => AluOp::Or,
+                BPF_AND => AluOp::And,
+                BPF_XOR => AluOp::Xor,
+                BPF_LSH => AluOp::Lsh,
+                BPF_RSH => AluOp::Rsh,
+                BPF_ARSH => AluOp::Arsh,
+                BPF_MOV => AluOp::Mov,
+                _ => return_errno_with_message!(Errno::EINVAL, "the BPF ALU is not supported"),
+            };
+            let bits = if is_64 { u64::BITS } else { u32::BITS };
+            match (op, alu_src()) {
+                (AluOp::Lsh | AluOp::Rsh | AluOp::Arsh, Src::K(k)) if k as u32 >= bits => {
+                    return_errno_with_message!(Errno::EINVAL, "the BPF shift is too large")
+                }
+                (_, src) => Insn::Alu {
+                    is_64,
+                    op,
+                    dst,
+                    src,
+                },
+            }
+        }
+        class @ (BPF_JMP | BPF_JMP32) => {
+            let is_64 = class == BPF_JMP;
+            let op = code & 0xf0;
+            match op {
+                BPF_CALL if is_64 => {
+                    if src != 0 || dst != 0 || off != 0 {
+                        return_errno_with_message!(
+                            Errno::EINVAL,
+                            "only BPF helper functions can be called"
+                        );
+                    }
+                    return Ok(Insn::Call(Helper::new(imm, prog_type)?));
+                }
+                BPF_EXIT if is_64 => return Ok(Insn::Exit),
+                _ => (),
+            }
+
+            let Ok(off) = u16::try_from(off) else {
+                return_errno_with_message!(Errno::EINVAL, "the BPF backward jump is not allowed");
+            };
+            if op == BPF_JA && is_64 {
+                return Ok(Insn::Jump(off));
+            }
+
+            let cond = match op {
+                BPF_JEQ => JumpCond::Eq,
+                BPF_JNE => JumpCond::Ne,
+                BPF_JGT => JumpCond::Gt,
+                BPF_JGE => JumpCond::Ge,
+                BPF_JLT => JumpCond::Lt,
+                BPF_JLE => JumpCond::Le,
+                BPF_JSGT => JumpCond::Sgt,
+                BPF_JSGE => JumpCond::Sge,
+                BPF_JSLT => JumpCond::Slt,
+                BPF_JSLE => JumpCond::Sle,
+                BPF_JSET => JumpCond::Set,
+                _ => return_errno_with_message!(Errno::EINVAL, "the BPF jump is not supported"),
+            };
+            Insn::JumpIf {
+                is_64,
+                cond,
+                dst,
+                src: alu_src(),
+                off,
+            }
+        }
+        BPF_LD => match code & 0xe0 {
+            BPF_IMM if code & 0x18 == BPF_DW => {
+                check_dst_writable()?;
+                if off != 0 || !matches!(src, 0 | BPF_PSEUDO_MAP_FD) {
+                    return_errno_with_message!(Errno::EINVAL, "the BPF 64-bit load is invalid");
+                }
+                Insn::LoadImm64 {
+                    dst,
+                    imm: imm as u32 as u64,
+                }
+            }
+            mode @ (BPF_ABS | BPF_IND) if code & 0x18 != BPF_DW => {
+                if prog_type != ProgType::SocketFilter {
+                    return_errno_with_message!(
+                        Errno::EINVAL,
+                        "the BPF packet load is only allowed in socket filters"
+                    );
+                }
+                if dst != 0 || off != 0 || (mode == BPF_ABS && src != 0) {
+                    return_errno_with_message!(Errno::EINVAL, "the BPF packet load is invalid");
+                }
+                Insn::LoadPacket {
+                    size: size(),
+                    src: (mode == BPF_IND).then_some(src),
+                    imm,
+                }
+            }
+            _ => return_errno_with_message!(Errno::EINVAL, "the BPF load is not supported"),
+        },
+        BPF_LDX => {
+            check_dst_writable()?;
+            if code & 0xe0 != BPF_MEM || imm != 0 {
+                return_errno_with_message!(Errno::EINVAL, "the BPF load is not supported");
+            }
+            Insn::Load {
+                size: size(),
+                dst,
+                src,
+                off,
+            }
+        }
+        BPF_ST | BPF_STX if code & 0xe0 == BPF_MEM => {
+            let src = if code & 0x07 == BPF_STX {
+                if imm != 0 {
+                    return_errno_with_message!(Errno::EINVAL, "the BPF store is invalid");
+                }
+                Src::X(src)
+            } else {
+                if src != 0 {
+                    return_errno_with_message!(Errno::EINVAL, "the BPF store is invalid");
+                }
+                Src::K(imm)
+            };
+            Insn::Store {
+                size: size(),
+                dst,
+                src,
+                off,
+            }
+        }
+        BPF_STX if code & 0xe0 == BPF_ATOMIC && matches!(size(), 4 | 8) => {
+            let op = match u8::try_from(imm & !BPF_FETCH) {
+                Ok(BPF_ADD) => AluOp::Add,
+                Ok(BPF_OR) => AluOp::Or,
+                Ok(BPF_AND) => AluOp::And,
+                Ok(BPF_XOR) => AluOp::Xor,
+                _ => {
+                    return_errno_with_message!(
+                        Errno::EINVAL,
+                        "the BPF atomic operation is not supported"
+                    )
+                }
+            };
+            let is_fetch = imm & BPF_FETCH != 0;
+            if is_fetch && src == BPF_REG_FP {
+                return_errno_with_message!(Errno::EACCES, "the BPF frame pointer is read-only");
+            }
+            Insn::Atomic {
+                size: size(),
+                op,
+                is_fetch,
+                dst,
+                src,
+                off,
+            }
+        }
+        _ => return_errno_with_message!(Errno::EINVAL, "the BPF instruction is not supported"),
+    };
+
+    Ok(insn)
+}
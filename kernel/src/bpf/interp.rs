@@ -0,0 +1,341 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The interpreter of the eBPF programs.
+//!
+//! The registers hold either scalars or pointers. A pointer consists of a region in the higher
+//! 32 bits and an offset in the region in the lower 32 bits, so that the memory accesses can be
+//! checked against the bounds of the region. A program that accesses memory out of bounds is
+//! aborted and returns zero, which is what Linux does if a packet load fails.
+
+use super::{
+    insn::{AluOp, Insn, JumpCond, Src, BPF_REG_FP, MAX_BPF_REG},
+    map::{BpfMap, MapValue},
+};
+use crate::prelude::*;
+
+/// The size of the stack of a program.
+const STACK_SIZE: usize = 512;
+
+// The regions of the pointers.
+const REGION_STACK: u32 = 1;
+/// The context of the program, which is read-only.
+const REGION_CTX: u32 = 2;
+/// The maps used by the program, which cannot be accessed directly.
+const REGION_MAP: u32 = 3;
+/// The map values returned by the helper functions, each of which has its own region.
+const REGION_VALUE_BASE: u32 = 4;
+
+/// A packet that consists of a header and a payload.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Packet<'a> {
+    header: &'a [u8],
+    payload: &'a [u8],
+}
+
+impl<'a> Packet<'a> {
+    pub(super) fn new(header: &'a [u8], payload: &'a [u8]) -> Self {
+        Self { header, payload }
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.header.len() + self.payload.len()
+    }
+
+    fn byte(&self, index: usize) -> Option<u8> {
+        match index.checked_sub(self.header.len()) {
+            None => Some(self.header[index]),
+            Some(index) => self.payload.get(index).copied(),
+        }
+    }
+
+    /// Loads the big-endian data of `size` bytes at the offset.
+    fn load_be(&self, offset: usize, size: usize) -> Option<u64> {
+        (offset..offset.checked_add(size)?).try_fold(0u64, |value, index| {
+            Some((value << 8) | self.byte(index)? as u64)
+        })
+    }
+}
+
+/// The state of a running program.
+pub(super) struct Vm<'a> {
+    regs: [u64; MAX_BPF_REG],
+    stack: [u8; STACK_SIZE],
+    ctx: &'a [u8],
+    packet: Option<Packet<'a>>,
+    maps: &'a [Arc<dyn BpfMap>],
+    values: Vec<Arc<MapValue>>,
+}
+
+impl<'a> Vm<'a> {
+    pub(super) fn new(
+        ctx: &'a [u8],
+        packet: Option<Packet<'a>>,
+        maps: &'a [Arc<dyn BpfMap>],
+    ) -> Self {
+        let mut regs = [0; MAX_BPF_REG];
+        regs[1] = pointer(REGION_CTX, 0);
+        regs[BPF_REG_FP as usize] = pointer(REGION_STACK, STACK_SIZE as u32);
+
+        Self {
+            regs,
+            stack: [0; STACK_SIZE],
+            ctx,
+            packet,
+            maps,
+            values: Vec::new(),
+        }
+    }
+
+    /// Runs the verified program and returns R0, or zero if the program is aborted.
+    pub(super) fn run(mut self, insns: &[Insn]) -> u64 {
+        self.exec(insns).unwrap_or(0)
+    }
+
+    fn exec(&mut self, insns: &[Insn]) -> Option<u64> {
+        // The jumps are always forward and are checked to be in the program, so the program
+        // terminates at an exit instruction.
+        let mut pc = 0;
+        loop {
+            let insn = insns[pc];
+            pc += 1;
+
+            match insn {
+                Insn::Alu {
+                    is_64,
+                    op,
+                    dst,
+                    src,
+                } => {
+                    let src = self.src_value(src);
+                    let dst = &mut self.regs[dst as usize];
+                    *dst = if is_64 {
+                        alu64(op, *dst, src)
+                    } else {
+                        alu32(op, *dst as u32, src as u32) as u64
+                    };
+                }
+                Insn::Neg { is_64, dst } => {
+                    let dst = &mut self.regs[dst as usize];
+                    *dst = if is_64 {
+                        dst.wrapping_neg()
+                    } else {
+                        (*dst as u32).wrapping_neg() as u64
+                    };
+                }
+                Insn::Endian { to_be, width, dst } => {
+                    let dst = &mut self.regs[dst as usize];
+                    *dst = match (width, to_be) {
+                        (16, false) => (*dst as u16).to_le() as u64,
+                        (16, true) => (*dst as u16).to_be() as u64,
+                        (32, false) => (*dst as u32).to_le() as u64,
+                        (32, true) => (*dst as u32).to_be() as u64,
+                        (_, false) => dst.to_le(),
+                        (_, true) => dst.to_be(),
+                    };
+                }
+                Insn::LoadImm64 { dst, imm } => {
+                    self.regs[dst as usize] = imm;
+                    pc += 1;
+                }
+                Insn::LoadMap { dst, map_index } => {
+                    self.regs[dst as usize] = pointer(REGION_MAP, map_index);
+                    pc += 1;
+                }
+                Insn::Imm64Tail => unreachable!("the second slot of a 64-bit load is executed"),
+                Insn::LoadPacket { size, src, imm } => {
+                    let base = src.map_or(0, |src| self.regs[src as usize] as u32 as i64);
+                    let offset = usize::try_from(base + imm as i64).ok()?;
+                    self.regs[0] = self.packet.as_ref()?.load_be(offset, size)?;
+                }
+                Insn::Load {
+                    size,
+                    dst,
+                    src,
+                    off,
+                } => {
+                    let addr = self.regs[src as usize].wrapping_add(off as i64 as u64);
+                    let mut bytes = [0u8; size_of::<u64>()];
+                    self.read(addr, &mut bytes[..size])?;
+                    self.regs[dst as usize] = u64::from_le_bytes(bytes);
+                }
+                Insn::Store {
+                    size,
+                    dst,
+                    src,
+                    off,
+                } => {
+                    let addr = self.regs[dst as usize].wrapping_add(off as i64 as u64);
+                    let value = self.src_value(src);
+                    self.write(addr, &value.to_le_bytes()[..size])?;
+                }
+                Insn::Atomic {
+                    size,
+                    op,
+                    is_fetch,
+                    dst,
+                    src,
+                    off,
+                } => {
+                    let addr = self.regs[dst as usize].wrapping_add(off as i64 as u64);
+                    let operand = self.regs[src as usize];
+                    // The map values are locked while being accessed, so the update is atomic.
+                    let old_value = self.with_mut(addr, size, |bytes| {
+                        let mut old_bytes = [0u8; size_of::<u64>()];
+                        old_bytes[..size].copy_from_slice(bytes);
+                        let old_value = u64::from_le_bytes(old_bytes);
+                        bytes.copy_from_slice(&alu64(op, old_value, operand).to_le_bytes()[..size]);
+                        old_value
+                    })?;
+                    if is_fetch {
+                        self.regs[src as usize] = old_value;
+                    }
+                }
+                Insn::Jump(off) => pc += off as usize,
+                Insn::JumpIf {
+                    is_64,
+                    cond,
+                    dst,
+                    src,
+                    off,
+                } => {
+                    let src = self.src_value(src);
+                    if compare(cond, self.regs[dst as usize], src, is_64) {
+                        pc += off as usize;
+                    }
+                }
+                Insn::Call(helper) => {
+                    let ret = helper.call(self)?;
+                    self.regs[0] = ret;
+                }
+                Insn::Exit => return Some(self.regs[0]),
+            }
+        }
+    }
+
+    fn src_value(&self, src: Src) -> u64 {
+        match src {
+            Src::K(imm) => imm as i64 as u64,
+            Src::X(reg) => self.regs[reg as usize],
+        }
+    }
+
+    /// Returns the value of the register, which is an argument of the helper functions.
+    pub(super) fn reg(&self, reg: usize) -> u64 {
+        self.regs[reg]
+    }
+
+    /// Returns the map that the pointer points to.
+    pub(super) fn map(&self, ptr: u64) -> Option<&'a Arc<dyn BpfMap>> {
+        if (ptr >> 32) as u32 != REGION_MAP {
+            return None;
+        }
+        self.maps.get(ptr as u32 as usize)
+    }
+
+    /// Makes the map value accessible to the program and returns the pointer to it.
+    pub(super) fn map_value_pointer(&mut self, value: Arc<MapValue>) -> u64 {
+        let region = REGION_VALUE_BASE + self.values.len() as u32;
+        self.values.push(value);
+        pointer(region, 0)
+    }
+
+    /// Reads the memory at the address into the buffer.
+    pub(super) fn read(&mut self, addr: u64, buf: &mut [u8]) -> Option<()> {
+        if (addr >> 32) as u32 == REGION_CTX {
+            let offset = addr as u32 as usize;
+            buf.copy_from_slice(self.ctx.get(offset..offset + buf.len())?);
+            return Some(());
+        }
+
+        self.with_mut(addr, buf.len(), |bytes| buf.copy_from_slice(bytes))
+    }
+
+    /// Writes the data to the memory at the address.
+    fn write(&mut self, addr: u64, data: &[u8]) -> Option<()> {
+        self.with_mut(addr, data.len(), |bytes| bytes.copy_from_slice(data))
+    }
+
+    /// Calls `f` with the writable memory of `len` bytes at the address.
+    fn with_mut<R>(&mut self, addr: u64, len: usize, f: impl FnOnce(&mut [u8]) -> R) -> Option<R> {
+        let region = (addr >> 32) as u32;
+        let offset = addr as u32 as usize;
+        let range = offset..offset + len;
+
+        if region == REGION_STACK {
+            return Some(f(self.stack.get_mut(range)?));
+        }
+
+        let value = self
+            .values
+            .get(region.checked_sub(REGION_VALUE_BASE)? as usize)?;
+        let mut bytes = value.lock();
+        Some(f(bytes.get_mut(range)?))
+    }
+}
+
+fn pointer(region: u32, offset: u32) -> u64 {
+    ((region as u64) << 32) | offset as u64
+}
+
+fn alu64(op: AluOp, dst: u64, src: u64) -> u64 {
+    match op {
+        AluOp::Add => dst.wrapping_add(src),
+        AluOp::Sub => dst.wrapping_sub(src),
+        AluOp::Mul => dst.wrapping_mul(src),
+        // Dividing by zero results in zero, and the modulo by zero leaves the destination
+        // unchanged, as specified in the instruction set.
+        AluOp::Div => dst.checked_div(src).unwrap_or(0),
+        AluOp::Mod => dst.checked_rem(src).unwrap_or(dst),
+        AluOp::Or => dst | src,
+        AluOp::And => dst & src,
+        AluOp::Xor => dst ^ src,
+        AluOp::Lsh => dst << (src & 63),
+        AluOp::Rsh => dst >> (src & 63),
+        AluOp::Arsh => ((dst as i64) >> (src & 63)) as u64,
+        AluOp::Mov => src,
+    }
+}
+
+fn alu32(op: AluOp, dst: u32, src: u32) -> u32 {
+    match op {
+        AluOp::Add => dst.wrapping_add(src),
+        AluOp::Sub => dst.wrapping_sub(src),
+        AluOp::Mul => dst.wrapping_mul(src),
+        AluOp::Div => dst.checked_div(src).unwrap_or(0),
+        AluOp::Mod => dst.checked_rem(src).unwrap_or(dst),
+        AluOp::Or => dst | src,
+        AluOp::And => dst & src,
+        AluOp::Xor => dst ^ src,
+        AluOp::Lsh => dst << (src & 31),
+        AluOp::Rsh => dst >> (src & 31),
+        AluOp::Arsh => ((dst as i32) >> (src & 31)) as u32,
+        AluOp::Mov => src,
+    }
+}
+
+fn compare(cond: JumpCond, dst: u64, src: u64, is_64: bool) -> bool {
+    let (dst, src, signed_dst, signed_src) = if is_64 {
+        (dst, src, dst as i64, src as i64)
+    } else {
+        (
+            dst as u32 as u64,
+            src as u32 as u64,
+            dst as i32 as i64,
+            src as i32 as i64,
+        )
+    };
+
+    match cond {
+        JumpCond::Eq => dst == src,
+        JumpCond::Ne => dst != src,
+        JumpCond::Gt => dst > src,
+        JumpCond::Ge => dst >= src,
+        JumpCond::Lt => dst < src,
+        JumpCond::Le => dst <= src,
+        JumpCond::Sgt => signed_dst > signed_src,
+        JumpCond::Sge => signed_dst >= signed_src,
+        JumpCond::Slt => signed_dst < signed_src,
+        JumpCond::Sle => signed_dst <= signed_src,
+        JumpCond::Set => dst & src != 0,
+    }
+}
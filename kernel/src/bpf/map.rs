@@ -0,0 +1,233 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::ops::Bound;
+
+use ostd::sync::LocalIrqDisabled;
+
+use crate::prelude::*;
+
+/// The maximum size of the keys, which is the same as the stack size of a program.
+const MAX_KEY_SIZE: u32 = 512;
+
+/// The maximum size of the values.
+const MAX_VALUE_SIZE: u32 = PAGE_SIZE as u32;
+
+/// The maximum total size of the values of an array map.
+const MAX_ARRAY_SIZE: usize = 64 * 1024 * 1024;
+
+/// The types of the maps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+#[repr(u32)]
+pub(super) enum MapType {
+    Hash = 1,
+    Array = 2,
+}
+
+/// The attributes of a map, which are specified when the map is created.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct MapAttr {
+    pub(super) map_type: MapType,
+    pub(super) key_size: u32,
+    pub(super) value_size: u32,
+    pub(super) max_entries: u32,
+}
+
+/// How to update an element of a map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+#[repr(u64)]
+pub(super) enum UpdateFlag {
+    /// Creates a new element or updates an existing one.
+    Any = 0,
+    /// Creates a new element only if it does not exist.
+    NoExist = 1,
+    /// Updates an existing element only.
+    Exist = 2,
+}
+
+/// A BPF map, which is a key-value store shared by the programs and the user space.
+///
+/// The keys and the values given to the methods must be of the sizes in [`MapAttr`].
+pub(super) trait BpfMap: Debug + Send + Sync {
+    /// Returns the attributes.
+    fn attr(&self) -> &MapAttr;
+
+    /// Looks up the value of the key.
+    fn lookup(&self, key: &[u8]) -> Option<Arc<MapValue>>;
+
+    /// Creates or updates the element of the key.
+    fn update(&self, key: &[u8], value: &[u8], flag: UpdateFlag) -> Result<()>;
+
+    /// Deletes the element of the key.
+    fn delete(&self, key: &[u8]) -> Result<()>;
+
+    /// Returns the key after the given key, or the first key if the given key is not found.
+    fn next_key(&self, key: Option<&[u8]>) -> Result<Vec<u8>>;
+}
+
+/// The value of an element of a map.
+///
+/// The programs access the value in place, and the accesses may happen in the interrupt
+/// context.
+#[derive(Debug)]
+pub(super) struct MapValue(SpinLock<Box<[u8]>, LocalIrqDisabled>);
+
+impl MapValue {
+    fn new(bytes: Box<[u8]>) -> Self {
+        Self(SpinLock::new(bytes))
+    }
+
+    pub(super) fn lock(&self) -> SpinLockGuard<'_, Box<[u8]>, LocalIrqDisabled> {
+        self.0.lock()
+    }
+}
+
+/// Creates a map after checking the attributes.
+pub(super) fn new_map(attr: MapAttr) -> Result<Arc<dyn BpfMap>> {
+    if attr.key_size == 0 || attr.value_size == 0 || attr.max_entries == 0 {
+        return_errno_with_message!(Errno::EINVAL, "the BPF map attributes are invalid");
+    }
+    if attr.key_size > MAX_KEY_SIZE || attr.value_size > MAX_VALUE_SIZE {
+        return_errno_with_message!(Errno::E2BIG, "the BPF map key or value is too large");
+    }
+
+    match attr.map_type {
+        MapType::Hash => Ok(Arc::new(HashMap {
+            attr,
+            entries: SpinLock::new(BTreeMap::new()),
+        })),
+        MapType::Array => {
+            if attr.key_size != size_of::<u32>() as u32 {
+                return_errno_with_message!(Errno::EINVAL, "the BPF array map key is not a u32");
+            }
+            if (attr.max_entries as usize).saturating_mul(attr.value_size as usize) > MAX_ARRAY_SIZE
+            {
+                return_errno_with_message!(Errno::ENOMEM, "the BPF array map is too large");
+            }
+
+            let values = (0..attr.max_entries)
+                .map(|_| Arc::new(MapValue::new(vec![0; attr.value_size as usize].into())))
+                .collect();
+            Ok(Arc::new(ArrayMap { attr, values }))
+        }
+    }
+}
+
+/// A hash map (`BPF_MAP_TYPE_HASH` in Linux).
+///
+/// The elements are kept in order, so the keys can be iterated in a stable order.
+#[derive(Debug)]
+struct HashMap {
+    attr: MapAttr,
+    entries: SpinLock<BTreeMap<Box<[u8]>, Arc<MapValue>>, LocalIrqDisabled>,
+}
+
+impl BpfMap for HashMap {
+    fn attr(&self) -> &MapAttr {
+        &self.attr
+    }
+
+    fn lookup(&self, key: &[u8]) -> Option<Arc<MapValue>> {
+        self.entries.lock().get(key).cloned()
+    }
+
+    fn update(&self, key: &[u8], value: &[u8], flag: UpdateFlag) -> Result<()> {
+        let mut entries = self.entries.lock();
+
+        match (entries.get(key), flag) {
+            (Some(_), UpdateFlag::NoExist) => {
+                return_errno_with_message!(Errno::EEXIST, "the BPF map element already exists")
+            }
+            (None, UpdateFlag::Exist) => {
+                return_errno_with_message!(Errno::ENOENT, "the BPF map element does not exist")
+            }
+            // The value is updated in place, so that the programs see the new value.
+            (Some(old_value), _) => old_value.lock().copy_from_slice(value),
+            (None, _) => {
+                if entries.len() >= self.attr.max_entries as usize {
+                    return_errno_with_message!(Errno::E2BIG, "the BPF map is full");
+                }
+                entries.insert(key.into(), Arc::new(MapValue::new(value.into())));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        match self.entries.lock().remove(key) {
+            Some(_) => Ok(()),
+            None => return_errno_with_message!(Errno::ENOENT, "the BPF map element does not exist"),
+        }
+    }
+
+    fn next_key(&self, key: Option<&[u8]>) -> Result<Vec<u8>> {
+        let entries = self.entries.lock();
+
+        let next_entry = match key {
+            Some(key) if entries.contains_key(key) => entries
+                .range::<[u8], _>((Bound::Excluded(key), Bound::Unbounded))
+                .next(),
+            _ => entries.iter().next(),
+        };
+        match next_entry {
+            Some((next_key, _)) => Ok(next_key.to_vec()),
+            None => return_errno_with_message!(Errno::ENOENT, "there are no more BPF map keys"),
+        }
+    }
+}
+
+/// An array map (`BPF_MAP_TYPE_ARRAY` in Linux).
+///
+/// The keys are the `u32` indexes, and all the elements exist and are zero initially.
+#[derive(Debug)]
+struct ArrayMap {
+    attr: MapAttr,
+    values: Box<[Arc<MapValue>]>,
+}
+
+impl ArrayMap {
+    fn index_of(&self, key: &[u8]) -> Option<usize> {
+        let index = u32::from_ne_bytes(key.try_into().ok()?) as usize;
+        (index < self.values.len()).then_some(index)
+    }
+}
+
+impl BpfMap for ArrayMap {
+    fn attr(&self) -> &MapAttr {
+        &self.attr
+    }
+
+    fn lookup(&self, key: &[u8]) -> Option<Arc<MapValue>> {
+        self.index_of(key).map(|index| self.values[index].clone())
+    }
+
+    fn update(&self, key: &[u8], value: &[u8], flag: UpdateFlag) -> Result<()> {
+        let Some(index) = self.index_of(key) else {
+            return_errno_with_message!(Errno::E2BIG, "the BPF array map index is out of range");
+        };
+        if flag == UpdateFlag::NoExist {
+            return_errno_with_message!(Errno::EEXIST, "the BPF array map elements always exist");
+        }
+
+        self.values[index].lock().copy_from_slice(value);
+        Ok(())
+    }
+
+    fn delete(&self, _key: &[u8]) -> Result<()> {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the BPF array map elements cannot be deleted"
+        );
+    }
+
+    fn next_key(&self, key: Option<&[u8]>) -> Result<Vec<u8>> {
+        let next_index = match key.and_then(|key| self.index_of(key)) {
+            Some(index) if index + 1 == self.values.len() => {
+                return_errno_with_message!(Errno::ENOENT, "there are no more BPF map keys")
+            }
+            Some(index) => index + 1,
+            None => 0,
+        };
+        Ok((next_index as u32).to_ne_bytes().to_vec())
+    }
+}
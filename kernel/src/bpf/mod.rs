@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A lightweight runtime of the eBPF programs.
+//!
+//! The programs are verified when they are loaded and run by an interpreter. They can use the
+//! hash maps and the array maps, and a few helper functions. Two types of programs are
+//! supported:
+//!  - Socket filters, which are attached to the UDP sockets with `SO_ATTACH_BPF` and filter the
+//!    incoming packets;
+//!  - Raw tracepoint programs, which are attached to the tracepoints with
+//!    `BPF_RAW_TRACEPOINT_OPEN` and run on the events of the tracepoints.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man2/bpf.2.html>
+
+mod file;
+mod helper;
+mod insn;
+mod interp;
+mod map;
+mod prog;
+
+pub use self::{
+    file::{BpfLinkFile, BpfMapFile, BpfProgFile},
+    insn::{BpfInsn, BPF_MAXINSNS},
+    prog::SocketFilter,
+};
@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_bigtcp::socket::PacketFilter;
+use aster_trace::{TraceProbe, Tracepoint, MAX_TRACE_ARGS};
+
+use super::{
+    file::BpfProgFile,
+    insn::{verify, BpfInsn, Insn},
+    interp::{Packet, Vm},
+    map::BpfMap,
+};
+use crate::{fs::file_table::FileDesc, prelude::*, process::posix_thread::AsPosixThread};
+
+/// The types of the programs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+#[repr(u32)]
+pub(super) enum ProgType {
+    /// A program that filters the incoming packets of a socket.
+    ///
+    /// The context is `struct __sk_buff`, of which only the `len` field is provided. The packet
+    /// data can be loaded by the packet load instructions.
+    SocketFilter = 1,
+    /// A program that runs on the events of a tracepoint.
+    ///
+    /// The context is the `u64` arguments of the event.
+    RawTracepoint = 17,
+}
+
+/// A verified program.
+#[derive(Debug)]
+pub(super) struct BpfProg {
+    prog_type: ProgType,
+    insns: Box<[Insn]>,
+    /// The maps used by the program, which are kept alive as long as the program.
+    maps: Vec<Arc<dyn BpfMap>>,
+}
+
+impl BpfProg {
+    /// Verifies the instructions and creates a program.
+    ///
+    /// The maps referred to by the program are resolved by `get_map` from their file
+    /// descriptors.
+    pub(super) fn new(
+        prog_type: ProgType,
+        insns: &[BpfInsn],
+        get_map: impl FnMut(i32) -> Result<Arc<dyn BpfMap>>,
+    ) -> Result<Self> {
+        let mut maps = Vec::new();
+        let insns = verify(insns, prog_type, get_map, &mut maps)?;
+
+        Ok(Self {
+            prog_type,
+            insns,
+            maps,
+        })
+    }
+
+    pub(super) fn prog_type(&self) -> ProgType {
+        self.prog_type
+    }
+
+    fn run(&self, ctx: &[u8], packet: Option<Packet>) -> u64 {
+        Vm::new(ctx, packet, &self.maps).run(&self.insns)
+    }
+}
+
+/// The size of `struct __sk_buff` in Linux.
+const SK_BUFF_SIZE: usize = 192;
+
+/// A BPF program that filters the incoming packets of a socket.
+///
+/// The return value of the program is the number of bytes of the packet to keep.
+#[derive(Debug)]
+pub struct SocketFilter(Arc<BpfProg>);
+
+impl SocketFilter {
+    /// Creates a filter with the program referred to by the file descriptor.
+    pub fn from_fd(fd: FileDesc) -> Result<Self> {
+        // The file table of the current thread may have been borrowed by the caller, so the file
+        // is looked up from the POSIX thread.
+        let file = {
+            let current = current_thread!();
+            let file_table = current.as_posix_thread().unwrap().file_table().lock();
+            let file_table = file_table.as_ref().unwrap().read();
+            file_table.get_file(fd)?.clone()
+        };
+
+        let Some(prog_file) = file.downcast_ref::<BpfProgFile>() else {
+            return_errno_with_message!(Errno::EINVAL, "the file is not a BPF program");
+        };
+        let prog = prog_file.prog();
+        if prog.prog_type() != ProgType::SocketFilter {
+            return_errno_with_message!(Errno::EINVAL, "the BPF program is not a socket filter");
+        }
+
+        Ok(Self(prog.clone()))
+    }
+}
+
+impl PacketFilter for SocketFilter {
+    fn filter(&self, header: &[u8], payload: &[u8]) -> usize {
+        let packet = Packet::new(header, payload);
+
+        let mut ctx = [0u8; SK_BUFF_SIZE];
+        ctx[..size_of::<u32>()].copy_from_slice(&(packet.len() as u32).to_ne_bytes());
+
+        self.0.run(&ctx, Some(packet)) as u32 as usize
+    }
+}
+
+/// A link that attaches a BPF program to a tracepoint.
+///
+/// The program is detached when the link is dropped.
+pub(super) struct TracepointLink {
+    tracepoint: &'static Tracepoint,
+    probe: Arc<dyn TraceProbe>,
+}
+
+impl TracepointLink {
+    /// Attaches the program to the tracepoint of the name.
+    ///
+    /// The name is either `<name>` or `<subsystem>:<name>`.
+    pub(super) fn attach(name: &str, prog: Arc<BpfProg>) -> Result<Self> {
+        if prog.prog_type() != ProgType::RawTracepoint {
+            return_errno_with_message!(Errno::EINVAL, "the BPF program is not for tracepoints");
+        }
+
+        let (subsystem, name) = match name.split_once(':') {
+            Some((subsystem, name)) => (Some(subsystem), name),
+            None => (None, name),
+        };
+        let Some(tracepoint) = aster_trace::all_tracepoints()
+            .into_iter()
+            .find(|tracepoint| {
+                tracepoint.name() == name
+                    && subsystem.is_none_or(|subsystem| tracepoint.subsystem() == subsystem)
+            })
+        else {
+            return_errno_with_message!(Errno::ENOENT, "the tracepoint does not exist");
+        };
+
+        let probe: Arc<dyn TraceProbe> = Arc::new(TracepointProbe(prog));
+        tracepoint.attach_probe(probe.clone());
+
+        Ok(Self { tracepoint, probe })
+    }
+}
+
+impl Drop for TracepointLink {
+    fn drop(&mut self) {
+        self.tracepoint.detach_probe(&self.probe);
+    }
+}
+
+struct TracepointProbe(Arc<BpfProg>);
+
+impl TraceProbe for TracepointProbe {
+    fn on_event(&self, args: &[u64]) {
+        let mut ctx = [0u8; MAX_TRACE_ARGS * size_of::<u64>()];
+        for (bytes, arg) in ctx.chunks_exact_mut(size_of::<u64>()).zip(args) {
+            bytes.copy_from_slice(&arg.to_ne_bytes());
+        }
+
+        self.0.run(&ctx[..args.len() * size_of::<u64>()], None);
+    }
+}
@@ -18,7 +18,7 @@ use crate::{
     },
     thread::Thread,
     util::{MultiRead, VmReaderArray},
-    vm::vmar::Vmar,
+    vm::{perms::VmPerms, vmar::Vmar},
 };
 
 /// The context that can be accessed from the current POSIX thread.
@@ -40,7 +40,10 @@ impl Context<'_> {
 
 /// The user's memory space of the current task.
 ///
-/// It provides methods to read from or write to the user space efficiently.
+/// It provides methods to read from or write to the user space efficiently. The methods never
+/// panic on invalid user pointers. The methods that copy data of fixed sizes (e.g.,
+/// [`Self::read_val`] and [`Self::write_bytes`]) validate the whole range against the mappings
+/// of the user space before copying, and fail with `EFAULT` if the range is not accessible.
 pub struct CurrentUserSpace<'a>(Ref<'a, Option<Vmar<Full>>>);
 
 /// Gets the [`CurrentUserSpace`] from the current task.
@@ -102,10 +105,7 @@ impl<'a> CurrentUserSpace<'a> {
     /// it returns `Ok`.
     pub fn read_bytes(&self, src: Vaddr, dest: &mut VmWriter<'_, Infallible>) -> Result<()> {
         let copy_len = dest.avail();
-
-        if copy_len > 0 {
-            check_vaddr(src)?;
-        }
+        self.check_range(src, copy_len, VmPerms::READ)?;
 
         let mut user_reader = self.reader(src, copy_len)?;
         user_reader.read_fallible(dest).map_err(|err| err.0)?;
@@ -114,14 +114,27 @@ impl<'a> CurrentUserSpace<'a> {
 
     /// Reads a value typed `Pod` from the user space of the current process.
     pub fn read_val<T: Pod>(&self, src: Vaddr) -> Result<T> {
-        if core::mem::size_of::<T>() > 0 {
-            check_vaddr(src)?;
-        }
+        self.check_range(src, core::mem::size_of::<T>(), VmPerms::READ)?;
 
         let mut user_reader = self.reader(src, core::mem::size_of::<T>())?;
         Ok(user_reader.read_val()?)
     }
 
+    /// Reads a structure whose size is specified by the user space.
+    ///
+    /// This is used for the extensible structures (e.g., `struct sched_attr`), which grow by
+    /// appending new fields. If `size` is smaller than the size of `T`, the missing fields are
+    /// zeros. If `size` is larger, the extra bytes belong to the fields unknown to the kernel,
+    /// so they must be zeros, otherwise this method fails with `E2BIG`.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/linux/uaccess.h#L358>
+    pub fn read_struct<T: Pod>(&self, src: Vaddr, size: usize) -> Result<T> {
+        self.check_range(src, size, VmPerms::READ)?;
+
+        let mut user_reader = self.reader(src, size)?;
+        read_struct_from(&mut user_reader)
+    }
+
     /// Writes bytes from the source `VmReader` to the user space of the current
     /// process.
     ///
@@ -133,10 +146,7 @@ impl<'a> CurrentUserSpace<'a> {
     /// `Ok`.
     pub fn write_bytes(&self, dest: Vaddr, src: &mut VmReader<'_, Infallible>) -> Result<()> {
         let copy_len = src.remain();
-
-        if copy_len > 0 {
-            check_vaddr(dest)?;
-        }
+        self.check_range(dest, copy_len, VmPerms::WRITE)?;
 
         let mut user_writer = self.writer(dest, copy_len)?;
         user_writer.write_fallible(src).map_err(|err| err.0)?;
@@ -145,14 +155,26 @@ impl<'a> CurrentUserSpace<'a> {
 
     /// Writes `val` to the user space of the current process.
     pub fn write_val<T: Pod>(&self, dest: Vaddr, val: &T) -> Result<()> {
-        if core::mem::size_of::<T>() > 0 {
-            check_vaddr(dest)?;
-        }
+        self.check_range(dest, core::mem::size_of::<T>(), VmPerms::WRITE)?;
 
         let mut user_writer = self.writer(dest, core::mem::size_of::<T>())?;
         Ok(user_writer.write_val(val)?)
     }
 
+    /// Atomically compares and exchanges a `u32` value in the user space of the current process.
+    ///
+    /// The value is replaced with `new_val` if it equals `old_val`. The previous value is
+    /// returned, so the exchange succeeds if and only if the returned value equals `old_val`.
+    pub fn atomic_compare_exchange(&self, dest: Vaddr, old_val: u32, new_val: u32) -> Result<u32> {
+        if dest % mem::align_of::<u32>() != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the user space address is not aligned");
+        }
+        self.check_range(dest, mem::size_of::<u32>(), VmPerms::READ | VmPerms::WRITE)?;
+
+        let user_writer = self.writer(dest, mem::size_of::<u32>())?;
+        Ok(user_writer.atomic_compare_exchange(old_val, new_val)?)
+    }
+
     /// Reads a C string from the user space of the current process.
     /// The length of the string should not exceed `max_len`,
     /// including the final `\0` byte.
@@ -164,6 +186,46 @@ impl<'a> CurrentUserSpace<'a> {
         let mut user_reader = self.reader(vaddr, max_len)?;
         user_reader.read_cstring()
     }
+
+    /// Checks whether the range of the user space can be accessed with the permissions.
+    ///
+    /// An empty range is always accessible.
+    fn check_range(&self, vaddr: Vaddr, len: usize, perms: VmPerms) -> Result<()> {
+        if len == 0 {
+            return Ok(());
+        }
+
+        check_vaddr(vaddr)?;
+        let end = vaddr
+            .checked_add(len)
+            .ok_or_else(|| Error::with_message(Errno::EFAULT, "the user space range overflows"))?;
+        self.root_vmar().check_access(vaddr..end, perms)
+    }
+}
+
+/// Reads a structure of `T` from all the remaining bytes of `reader`.
+///
+/// See [`CurrentUserSpace::read_struct`] for how the bytes are converted if their size differs
+/// from the size of `T`.
+fn read_struct_from<T: Pod>(reader: &mut VmReader<'_, Fallible>) -> Result<T> {
+    let mut val = T::new_zeroed();
+    let len = reader.remain().min(mem::size_of::<T>());
+    reader
+        .read_fallible(&mut VmWriter::from(&mut val.as_bytes_mut()[..len]))
+        .map_err(|err| err.0)?;
+
+    while reader.remain() >= mem::size_of::<usize>() {
+        if reader.read_val::<usize>()? != 0 {
+            return_errno_with_message!(Errno::E2BIG, "the unknown fields are not zeros");
+        }
+    }
+    while reader.has_remain() {
+        if reader.read_val::<u8>()? != 0 {
+            return_errno_with_message!(Errno::E2BIG, "the unknown fields are not zeros");
+        }
+    }
+
+    Ok(val)
 }
 
 /// A trait providing the ability to read a C string from the user space.
@@ -395,4 +457,30 @@ mod test {
             .read_cstring()
             .is_err_and(|err| err.error() == Errno::EFAULT));
     }
+
+    #[ktest]
+    fn read_struct_with_different_sizes() {
+        let bytes: Vec<u8> = (1..=16).collect();
+        let read_struct = |bytes: &[u8]| {
+            let mut reader = VmReader::from(bytes).to_fallible();
+            read_struct_from::<[u8; 8]>(&mut reader)
+        };
+
+        // The size is the same.
+        assert_eq!(read_struct(&bytes[..8]).unwrap(), [1, 2, 3, 4, 5, 6, 7, 8]);
+
+        // The size is smaller, so the missing fields are zeros.
+        assert_eq!(read_struct(&bytes[..3]).unwrap(), [1, 2, 3, 0, 0, 0, 0, 0]);
+        assert_eq!(read_struct(&[]).unwrap(), [0; 8]);
+
+        // The size is larger, so the extra bytes must be zeros.
+        let mut larger = bytes[..8].to_vec();
+        larger.resize(19, 0);
+        assert_eq!(read_struct(&larger).unwrap(), [1, 2, 3, 4, 5, 6, 7, 8]);
+        for i in 8..larger.len() {
+            let mut larger = larger.clone();
+            larger[i] = 1;
+            assert!(read_struct(&larger).is_err_and(|err| err.error() == Errno::E2BIG));
+        }
+    }
 }
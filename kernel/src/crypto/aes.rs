@@ -0,0 +1,90 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aes::{
+    cipher::generic_array::GenericArray, Aes128, Aes256, BlockDecrypt, BlockEncrypt, NewBlockCipher,
+};
+#[cfg(target_arch = "x86_64")]
+use ostd::cpu::aesni::AesNiKey;
+
+use super::CipherContext;
+use crate::prelude::*;
+
+/// The AES block cipher with a 128-bit or 256-bit key.
+pub struct Aes {
+    backend: Backend,
+}
+
+enum Backend {
+    #[cfg(target_arch = "x86_64")]
+    Ni(AesNiKey),
+    Soft128(Aes128),
+    Soft256(Aes256),
+}
+
+impl Aes {
+    /// The size of a block in bytes.
+    pub const BLOCK_SIZE: usize = 16;
+
+    /// Creates a cipher with the key, which must be 16 or 32 bytes.
+    pub fn new(key: &[u8]) -> Result<Self> {
+        if key.len() != 16 && key.len() != 32 {
+            return_errno_with_message!(Errno::EINVAL, "the AES key size is invalid");
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        if AesNiKey::is_supported() {
+            let ctx = CipherContext::new();
+            let key = AesNiKey::new(key, &ctx.fpu_guard).unwrap();
+            return Ok(Self {
+                backend: Backend::Ni(key),
+            });
+        }
+
+        let backend = if key.len() == 16 {
+            Backend::Soft128(Aes128::new(GenericArray::from_slice(key)))
+        } else {
+            Backend::Soft256(Aes256::new(GenericArray::from_slice(key)))
+        };
+        Ok(Self { backend })
+    }
+
+    /// Encrypts the block in place.
+    #[cfg_attr(not(target_arch = "x86_64"), expect(unused_variables))]
+    pub fn encrypt_block(&self, block: &mut [u8; Self::BLOCK_SIZE], ctx: &CipherContext) {
+        match &self.backend {
+            #[cfg(target_arch = "x86_64")]
+            Backend::Ni(key) => key.encrypt_block(block, &ctx.fpu_guard),
+            Backend::Soft128(cipher) => cipher.encrypt_block(GenericArray::from_mut_slice(block)),
+            Backend::Soft256(cipher) => cipher.encrypt_block(GenericArray::from_mut_slice(block)),
+        }
+    }
+
+    /// Decrypts the block in place.
+    #[cfg_attr(not(target_arch = "x86_64"), expect(unused_variables))]
+    pub fn decrypt_block(&self, block: &mut [u8; Self::BLOCK_SIZE], ctx: &CipherContext) {
+        match &self.backend {
+            #[cfg(target_arch = "x86_64")]
+            Backend::Ni(key) => key.decrypt_block(block, &ctx.fpu_guard),
+            Backend::Soft128(cipher) => cipher.decrypt_block(GenericArray::from_mut_slice(block)),
+            Backend::Soft256(cipher) => cipher.decrypt_block(GenericArray::from_mut_slice(block)),
+        }
+    }
+
+    /// Returns whether the cipher uses the instructions of the CPU.
+    pub fn is_accelerated(&self) -> bool {
+        match &self.backend {
+            #[cfg(target_arch = "x86_64")]
+            Backend::Ni(_) => true,
+            Backend::Soft128(_) | Backend::Soft256(_) => false,
+        }
+    }
+}
+
+impl Debug for Aes {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        // The key is a secret.
+        f.debug_struct("Aes")
+            .field("is_accelerated", &self.is_accelerated())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,46 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The cryptographic algorithms used by the kernel.
+//!
+//! The ciphers use the instructions of the CPU if they are available, e.g., AES-NI on x86-64, and
+//! fall back to the software implementations otherwise. Since the instructions operate on the
+//! SIMD registers, the ciphers can only be used with a [`CipherContext`].
+
+mod aes;
+mod sha256;
+mod xts;
+
+#[cfg(target_arch = "x86_64")]
+use ostd::cpu::context::KernelFpuGuard;
+
+pub use self::{aes::Aes, sha256::Sha256, xts::AesXts};
+
+/// The context in which the ciphers can be used.
+///
+/// The preemption is disabled while the context is alive, so it should not be held for a long
+/// time. The context cannot be created in the interrupt context.
+pub struct CipherContext {
+    #[cfg(target_arch = "x86_64")]
+    fpu_guard: KernelFpuGuard,
+}
+
+impl CipherContext {
+    /// Creates a context.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if it is called in the interrupt context, or if another context is
+    /// alive on the current CPU.
+    pub fn new() -> Self {
+        Self {
+            #[cfg(target_arch = "x86_64")]
+            fpu_guard: KernelFpuGuard::new(),
+        }
+    }
+}
+
+impl Default for CipherContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,84 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{Aes, CipherContext};
+use crate::prelude::*;
+
+/// The AES cipher in the XTS mode (IEEE 1619), which is used to encrypt the sectors of disks.
+///
+/// The data are encrypted in units of [`Self::DATA_UNIT_SIZE`] bytes, and each unit is tweaked
+/// by its index, i.e., the sector number in the little-endian order (`plain64` in Linux).
+#[derive(Debug)]
+pub struct AesXts {
+    data_cipher: Aes,
+    tweak_cipher: Aes,
+}
+
+impl AesXts {
+    /// The size of a data unit in bytes.
+    pub const DATA_UNIT_SIZE: usize = 512;
+
+    /// Creates a cipher with the key, which must be 32 or 64 bytes.
+    ///
+    /// The first half of the key is used to encrypt the data, and the second half is used to
+    /// encrypt the tweaks.
+    pub fn new(key: &[u8]) -> Result<Self> {
+        if key.len() != 32 && key.len() != 64 {
+            return_errno_with_message!(Errno::EINVAL, "the AES-XTS key size is invalid");
+        }
+
+        let (data_key, tweak_key) = key.split_at(key.len() / 2);
+        Ok(Self {
+            data_cipher: Aes::new(data_key)?,
+            tweak_cipher: Aes::new(tweak_key)?,
+        })
+    }
+
+    /// Encrypts the units in place, the first of which has the index `unit_index`.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the length of the data is not a multiple of the size of a unit.
+    pub fn encrypt(&self, unit_index: u64, data: &mut [u8], ctx: &CipherContext) {
+        self.crypt(unit_index, data, ctx, |block| {
+            self.data_cipher.encrypt_block(block, ctx)
+        });
+    }
+
+    /// Decrypts the units in place, the first of which has the index `unit_index`.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the length of the data is not a multiple of the size of a unit.
+    pub fn decrypt(&self, unit_index: u64, data: &mut [u8], ctx: &CipherContext) {
+        self.crypt(unit_index, data, ctx, |block| {
+            self.data_cipher.decrypt_block(block, ctx)
+        });
+    }
+
+    fn crypt<F>(&self, unit_index: u64, data: &mut [u8], ctx: &CipherContext, crypt_block: F)
+    where
+        F: Fn(&mut [u8; Aes::BLOCK_SIZE]),
+    {
+        assert_eq!(data.len() % Self::DATA_UNIT_SIZE, 0);
+
+        for (i, unit) in data.chunks_exact_mut(Self::DATA_UNIT_SIZE).enumerate() {
+            let mut tweak = (unit_index.wrapping_add(i as u64) as u128).to_le_bytes();
+            self.tweak_cipher.encrypt_block(&mut tweak, ctx);
+            let mut tweak = u128::from_le_bytes(tweak);
+
+            for block in unit.chunks_exact_mut(Aes::BLOCK_SIZE) {
+                let block: &mut [u8; Aes::BLOCK_SIZE] = block.try_into().unwrap();
+                xor_block(block, tweak);
+                crypt_block(block);
+                xor_block(block, tweak);
+
+                // Multiply the tweak by the primitive element in GF(2^128).
+                tweak = (tweak << 1) ^ ((tweak >> 127) * 0x87);
+            }
+        }
+    }
+}
+
+fn xor_block(block: &mut [u8; Aes::BLOCK_SIZE], value: u128) {
+    *block = (u128::from_le_bytes(*block) ^ value).to_le_bytes();
+}
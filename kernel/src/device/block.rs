@@ -0,0 +1,330 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The block device nodes, e.g., `/dev/vda` and its partitions `/dev/vda1`, `/dev/vda2`, etc.
+//!
+//! The partition tables of the block devices are read when the nodes are created. Like Linux,
+//! the `BLKRRPART` ioctl re-reads the partition table of a device and recreates the nodes of its
+//! partitions, which fails with `EBUSY` if any of the partitions is mounted or opened. This is
+//! required after the partition table is written, e.g., by `fdisk`.
+//!
+//! The nodes can be read and written at any position. The I/O is done synchronously without the
+//! page cache.
+
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use align_ext::AlignExt;
+use aster_block::{
+    partition::{Partition, PartitionError},
+    BlockDevice, SECTOR_SIZE,
+};
+use ostd::mm::VmIo;
+
+use super::*;
+use crate::{
+    current_userspace,
+    events::IoEvents,
+    fs::{
+        device::delete_node,
+        fs_resolver::{FsPath, FsResolver},
+        inode_handle::FileIo,
+        utils::{InodeType, IoctlCmd},
+    },
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        posix_thread::AsPosixThread,
+        signal::{PollHandle, Pollable},
+    },
+};
+
+/// The major number of the block devices, which is in the range of the dynamic majors in Linux.
+const BLOCK_MAJOR: u32 = 254;
+/// The major number of the partitions whose numbers are too large to follow the minor number of
+/// the device, which is the same as Linux.
+const BLOCK_EXT_MAJOR: u32 = 259;
+/// The number of minor numbers reserved for each device, i.e., the device and its first 15
+/// partitions.
+const MINORS_PER_DEVICE: u32 = 16;
+
+/// The maximum number of bytes read or written at once.
+const MAX_IO_BYTES: usize = 128 * 1024;
+
+static NEXT_EXT_MINOR: AtomicU32 = AtomicU32::new(0);
+
+/// The names of the block devices, indexed by the device IDs of their nodes.
+static DEVICE_NAMES: Mutex<BTreeMap<u64, String>> = Mutex::new(BTreeMap::new());
+
+pub(super) fn init() -> Result<()> {
+    let devices = aster_block::all_devices()
+        .into_iter()
+        .filter(|(_, device)| device.downcast_ref::<Partition>().is_none());
+
+    for (index, (name, _)) in devices.enumerate() {
+        let id = DeviceId::new(BLOCK_MAJOR, index as u32 * MINORS_PER_DEVICE);
+        let node = add_block_node(&name, id)?;
+
+        match aster_block::rescan_partitions(&name) {
+            Ok(partition_names) => node.add_partition_nodes(&partition_names)?,
+            Err(err) => warn!("failed to read the partition table of {}: {:?}", name, err),
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the block device whose node has the device ID.
+pub fn get_block_device(id: DeviceId) -> Option<Arc<dyn BlockDevice>> {
+    let name = DEVICE_NAMES.lock().get(&u64::from(id))?.clone();
+    aster_block::get_device(&name)
+}
+
+/// Returns the node of the block device with the device ID, which is used by `mknod`.
+pub(super) fn get_node(id: DeviceId) -> Option<Arc<dyn Device>> {
+    let name = DEVICE_NAMES.lock().get(&u64::from(id))?.clone();
+    Some(Arc::new(BlockDeviceNode { name, id }))
+}
+
+/// Looks up the block device by the path of its node (e.g., `/dev/vda1`), its device number
+/// (e.g., `254:1`), or its name (e.g., `vda1`).
+pub(super) fn lookup_block_device(name: &str) -> Option<Arc<dyn BlockDevice>> {
+    if name.starts_with('/') {
+        let dentry = FsResolver::new()
+            .lookup(&FsPath::try_from(name).ok()?)
+            .ok()?;
+        if dentry.type_() != InodeType::BlockDevice {
+            return None;
+        }
+        return get_block_device(DeviceId::from(dentry.metadata().rdev));
+    }
+
+    if let Some((major, minor)) = name.split_once(':') {
+        return get_block_device(DeviceId::new(major.parse().ok()?, minor.parse().ok()?));
+    }
+
+    aster_block::get_device(name)
+}
+
+/// Adds the node of the block device, whose name is also the path under `/dev`.
+pub(super) fn add_block_node(name: &str, id: DeviceId) -> Result<BlockDeviceNode> {
+    let node = BlockDeviceNode {
+        name: name.to_string(),
+        id,
+    };
+    add_node(Arc::new(node.clone()), name)?;
+    DEVICE_NAMES.lock().insert(id.into(), name.to_string());
+    Ok(node)
+}
+
+/// Adds another node of the block device at the path under `/dev`, e.g., `/dev/mapper/<name>`
+/// for `/dev/dm-<N>`.
+pub(super) fn add_block_alias(path: &str, name: &str, id: DeviceId) -> Result<()> {
+    let node = BlockDeviceNode {
+        name: name.to_string(),
+        id,
+    };
+    add_node(Arc::new(node), path)?;
+    Ok(())
+}
+
+/// Deletes the node of the block device, whose name is also the path under `/dev`.
+pub(super) fn delete_block_node(name: &str, id: DeviceId) -> Result<()> {
+    DEVICE_NAMES.lock().remove(&u64::from(id));
+    delete_node(name)
+}
+
+/// The node of a block device.
+///
+/// The node refers to the device by its name, so the partitions are in use only if they are
+/// mounted or opened.
+#[derive(Clone)]
+pub(super) struct BlockDeviceNode {
+    name: String,
+    id: DeviceId,
+}
+
+impl BlockDeviceNode {
+    fn add_partition_nodes(&self, partition_names: &[String]) -> Result<()> {
+        for partition_name in partition_names {
+            // The partition number is the suffix of the name, e.g., `1` in `vda1`.
+            let number = partition_name[self.name.len()..]
+                .trim_start_matches('p')
+                .parse::<u32>()
+                .unwrap();
+            let id = if number < MINORS_PER_DEVICE {
+                DeviceId::new(BLOCK_MAJOR, self.id.minor() + number)
+            } else {
+                DeviceId::new(
+                    BLOCK_EXT_MAJOR,
+                    NEXT_EXT_MINOR.fetch_add(1, Ordering::Relaxed),
+                )
+            };
+            add_block_node(partition_name, id)?;
+        }
+
+        Ok(())
+    }
+
+    fn rescan_partitions(&self) -> Result<()> {
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        if !credentials.has_capability(CapSet::SYS_ADMIN) {
+            return_errno_with_message!(
+                Errno::EACCES,
+                "the current thread does not have the CAP_SYS_ADMIN capability"
+            );
+        }
+
+        let old_partition_names = aster_block::partitions_of(&self.name);
+        let new_partition_names = aster_block::rescan_partitions(&self.name)?;
+
+        DEVICE_NAMES
+            .lock()
+            .retain(|_, name| !old_partition_names.contains(name));
+        for partition_name in old_partition_names.iter() {
+            // The node may have been removed by the user.
+            let _ = delete_node(partition_name);
+        }
+        self.add_partition_nodes(&new_partition_names)
+    }
+}
+
+impl Device for BlockDeviceNode {
+    fn type_(&self) -> DeviceType {
+        DeviceType::BlockDevice
+    }
+
+    fn id(&self) -> DeviceId {
+        self.id
+    }
+
+    fn open(&self) -> Result<Option<Arc<dyn FileIo>>> {
+        let Some(device) = aster_block::get_device(&self.name) else {
+            return_errno_with_message!(Errno::ENXIO, "the block device does not exist");
+        };
+
+        Ok(Some(Arc::new(BlockDeviceFile {
+            node: self.clone(),
+            device,
+        })))
+    }
+}
+
+impl Pollable for BlockDeviceNode {
+    fn poll(&self, _mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        IoEvents::empty()
+    }
+}
+
+impl FileIo for BlockDeviceNode {
+    fn read(&self, _writer: &mut VmWriter) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "the block device is not opened");
+    }
+
+    fn write(&self, _reader: &mut VmReader) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "the block device is not opened");
+    }
+}
+
+/// An opened block device.
+struct BlockDeviceFile {
+    node: BlockDeviceNode,
+    device: Arc<dyn BlockDevice>,
+}
+
+impl BlockDeviceFile {
+    fn size(&self) -> usize {
+        self.device.metadata().nr_sectors * SECTOR_SIZE
+    }
+
+    /// Returns the number of bytes to access at the offset and the sector-aligned range that
+    /// covers the bytes.
+    fn io_range(&self, offset: usize, len: usize) -> (usize, Range<usize>) {
+        let len = len.min(MAX_IO_BYTES).min(self.size() - offset);
+        let range = offset.align_down(SECTOR_SIZE)..(offset + len).align_up(SECTOR_SIZE);
+        (len, range)
+    }
+}
+
+impl Pollable for BlockDeviceFile {
+    fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        let events = IoEvents::IN | IoEvents::OUT;
+        events & mask
+    }
+}
+
+impl FileIo for BlockDeviceFile {
+    fn read(&self, writer: &mut VmWriter) -> Result<usize> {
+        self.read_at(0, writer)
+    }
+
+    fn write(&self, reader: &mut VmReader) -> Result<usize> {
+        self.write_at(0, reader)
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        true
+    }
+
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        if offset >= self.size() {
+            return Ok(0);
+        }
+
+        let (len, range) = self.io_range(offset, writer.avail());
+        let mut buf = vec![0u8; range.len()];
+        self.device.read_bytes(range.start, &mut buf)?;
+
+        let start = offset - range.start;
+        writer.write_fallible(&mut VmReader::from(&buf[start..start + len]))?;
+        Ok(len)
+    }
+
+    fn write_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        if offset >= self.size() {
+            return_errno_with_message!(Errno::ENOSPC, "the offset is beyond the end of the device");
+        }
+
+        let (len, range) = self.io_range(offset, reader.remain());
+        let mut buf = vec![0u8; range.len()];
+        let start = offset - range.start;
+        // The partial sectors at both ends are read first so that their other bytes are kept.
+        if start != 0 || len != range.len() {
+            self.device.read_bytes(range.start, &mut buf)?;
+        }
+        reader.read_fallible(&mut VmWriter::from(&mut buf[start..start + len]))?;
+        self.device.write_bytes(range.start, &buf)?;
+
+        Ok(len)
+    }
+
+    fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
+        match cmd {
+            IoctlCmd::BLKRRPART => self.node.rescan_partitions()?,
+            IoctlCmd::BLKSSZGET => current_userspace!().write_val(arg, &(SECTOR_SIZE as i32))?,
+            IoctlCmd::BLKGETSIZE64 => current_userspace!().write_val(arg, &(self.size() as u64))?,
+            _ => return_errno_with_message!(Errno::ENOTTY, "unsupported ioctl"),
+        }
+        Ok(0)
+    }
+}
+
+impl From<PartitionError> for Error {
+    fn from(err: PartitionError) -> Self {
+        match err {
+            PartitionError::NotFound => {
+                Error::with_message(Errno::ENXIO, "the block device does not exist")
+            }
+            PartitionError::IsPartition => {
+                Error::with_message(Errno::EINVAL, "the block device is a partition")
+            }
+            PartitionError::IoError => {
+                Error::with_message(Errno::EIO, "the partition table cannot be read")
+            }
+            PartitionError::Busy => {
+                Error::with_message(Errno::EBUSY, "the partitions of the device are in use")
+            }
+        }
+    }
+}
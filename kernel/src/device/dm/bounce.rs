@@ -0,0 +1,78 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The bounce buffers used by the targets that transform the data (e.g., `crypt`).
+//!
+//! The data of a request are copied into a buffer, transformed, and then sent to the underlying
+//! device in a separate request, or vice versa.
+
+use aster_block::{
+    bio::{Bio, BioDirection, BioSegment, BioStatus, BioType, SubmittedBio},
+    id::Sid,
+    BlockDevice, BLOCK_SIZE,
+};
+use ostd::mm::VmIo;
+
+use crate::prelude::*;
+
+/// Reads the sectors starting from the sector `sid` of the device into the buffer.
+pub(super) fn read_sectors(device: &dyn BlockDevice, sid: Sid, buf: &mut [u8]) -> BioStatus {
+    let (_segment, slice) = alloc_segment(buf.len(), BioDirection::FromDevice);
+    let status = submit_and_wait(device, BioType::Read, sid, slice.clone());
+    if status == BioStatus::Complete {
+        slice.read_bytes(0, buf).unwrap();
+    }
+    status
+}
+
+/// Writes the buffer to the sectors starting from the sector `sid` of the device.
+pub(super) fn write_sectors(device: &dyn BlockDevice, sid: Sid, buf: &[u8]) -> BioStatus {
+    let (_segment, slice) = alloc_segment(buf.len(), BioDirection::ToDevice);
+    slice.write_bytes(0, buf).unwrap();
+    submit_and_wait(device, BioType::Write, sid, slice)
+}
+
+/// Copies the data of the request into the buffer.
+pub(super) fn copy_from_bio(bio: &SubmittedBio, buf: &mut [u8]) {
+    let mut offset = 0;
+    for segment in bio.segments() {
+        // The device can only read the segments, so their memory is read directly.
+        let dma_slice = segment.inner_dma_slice();
+        let len = segment.nbytes();
+        dma_slice
+            .stream()
+            .segment()
+            .read_bytes(dma_slice.offset(), &mut buf[offset..offset + len])
+            .unwrap();
+        offset += len;
+    }
+}
+
+/// Copies the buffer into the segments of the request.
+pub(super) fn copy_to_bio(bio: &SubmittedBio, buf: &[u8]) {
+    let mut offset = 0;
+    for segment in bio.segments() {
+        let len = segment.nbytes();
+        segment.write_bytes(0, &buf[offset..offset + len]).unwrap();
+        offset += len;
+    }
+}
+
+/// Allocates a segment of `nbytes` bytes.
+///
+/// The segment is a slice of the first returned segment, which returns the pooled memory when it
+/// is dropped, so the first one must outlive the second one.
+fn alloc_segment(nbytes: usize, direction: BioDirection) -> (BioSegment, BioSegment) {
+    let segment = BioSegment::alloc(nbytes.div_ceil(BLOCK_SIZE), direction);
+    let slice = segment.slice(0..nbytes);
+    (segment, slice)
+}
+
+fn submit_and_wait(
+    device: &dyn BlockDevice,
+    type_: BioType,
+    sid: Sid,
+    segment: BioSegment,
+) -> BioStatus {
+    let bio = Bio::new(type_, sid, vec![segment], None);
+    bio.submit_and_wait(device).unwrap_or(BioStatus::IoError)
+}
@@ -0,0 +1,224 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `crypt` target, which encrypts the sectors transparently like `dm-crypt` of Linux.
+//!
+//! The parameters of the target are the same as Linux:
+//!
+//! ```text
+//! <cipher> <key> <iv_offset> <device> <offset>
+//! ```
+//!
+//! Only the `aes-xts-plain64` cipher is supported. The `<key>` is either the key in hexadecimal,
+//! or `:<key_size>:<user|logon>:<key_description>`, which refers to a key in the keyring. The
+//! `<iv_offset>` is added to the sector numbers, which are used as the tweaks of AES-XTS.
+//!
+//! For example, the following command creates an encrypted device on a partition with a 512-bit
+//! key that has been added to the keyring with `add_key`:
+//!
+//! ```text
+//! dmsetup create secret --table "0 8192 crypt aes-xts-plain64 :64:logon:dm:key 0 /dev/vda1 0"
+//! ```
+
+use core::ops::Range;
+
+use aster_block::{
+    bio::{BioEnqueueError, BioStatus, BioType, SubmittedBio},
+    dm::{DmError, Target, TargetPiece},
+    id::Sid,
+    BlockDevice, BlockDeviceMeta, BLOCK_SIZE,
+};
+
+use super::{
+    bounce::{copy_from_bio, copy_to_bio, read_sectors, write_sectors},
+    parse_hex,
+};
+use crate::{
+    crypto::{AesXts, CipherContext},
+    keyring::{self, KeyType},
+    prelude::*,
+    thread::work_queue::{submit_work_func, WorkPriority},
+};
+
+/// The target that encrypts its sectors and stores them on the consecutive sectors of a device.
+#[derive(Debug)]
+pub(super) struct Crypt {
+    device: Arc<CryptDevice>,
+}
+
+impl Crypt {
+    /// Creates a target of `len` sectors that starts from the sector `start` of the device.
+    pub(super) fn new(
+        cipher: &str,
+        key: &str,
+        iv_offset: u64,
+        device: Arc<dyn BlockDevice>,
+        start: u64,
+        len: u64,
+    ) -> Result<Self> {
+        if cipher != "aes-xts-plain64" {
+            return_errno_with_message!(Errno::EINVAL, "the cipher is not supported");
+        }
+        let nr_sectors = device.metadata().nr_sectors as u64;
+        if start.checked_add(len).is_none_or(|end| end > nr_sectors) {
+            return Err(DmError::DeviceTooSmall.into());
+        }
+
+        let mut key = parse_key(key)?;
+        let cipher = AesXts::new(&key);
+        key.fill(0);
+        let cipher = cipher?;
+
+        let device = Arc::new_cyclic(|weak_self| CryptDevice {
+            weak_self: weak_self.clone(),
+            cipher,
+            iv_offset,
+            device,
+            start,
+            len,
+        });
+        Ok(Self { device })
+    }
+}
+
+impl Target for Crypt {
+    fn type_name(&self) -> &'static str {
+        "crypt"
+    }
+
+    fn devices(&self) -> Vec<Arc<dyn BlockDevice>> {
+        vec![self.device.clone() as Arc<dyn BlockDevice>]
+    }
+
+    fn map(&self, sid_range: Range<u64>, pieces: &mut Vec<TargetPiece>) {
+        // The sectors are sent to the internal device, which encrypts them and forwards them to
+        // the underlying device.
+        pieces.push(TargetPiece {
+            device: self.device.clone(),
+            sid_range: Sid::new(sid_range.start)..Sid::new(sid_range.end),
+            target_sid: Sid::new(sid_range.start),
+        });
+    }
+}
+
+/// Parses the key in hexadecimal or the reference to a key in the keyring.
+fn parse_key(key: &str) -> Result<Vec<u8>> {
+    if let Some(key_ref) = key.strip_prefix(':') {
+        let mut fields = key_ref.splitn(3, ':');
+        let (Some(key_size), Some(type_name), Some(description)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            return_errno_with_message!(Errno::EINVAL, "the key reference is invalid");
+        };
+        let type_ = match type_name {
+            "user" => KeyType::User,
+            "logon" => KeyType::Logon,
+            _ => return_errno_with_message!(Errno::EINVAL, "the key type is not supported"),
+        };
+
+        let Some(key) = keyring::lookup_key(type_, description) else {
+            return_errno_with_message!(Errno::ENOKEY, "the key does not exist");
+        };
+        if key_size.parse::<usize>().ok() != Some(key.payload().len()) {
+            return_errno_with_message!(Errno::EINVAL, "the key size mismatches");
+        }
+        return Ok(key.payload().to_vec());
+    }
+
+    parse_hex(key)
+}
+
+/// The internal device of a `crypt` target, whose sectors are the sectors of the target.
+///
+/// The requests are handled in the work queue, since they must wait for the requests to the
+/// underlying device before they are decrypted.
+#[derive(Debug)]
+struct CryptDevice {
+    weak_self: Weak<Self>,
+    cipher: AesXts,
+    iv_offset: u64,
+    device: Arc<dyn BlockDevice>,
+    start: u64,
+    len: u64,
+}
+
+impl CryptDevice {
+    fn handle(&self, bio: &SubmittedBio) -> BioStatus {
+        let sid_range = bio.sid_range();
+        let sid = sid_range.start + self.start;
+        let mut buf = vec![0u8; sid_range.end.to_offset() - sid_range.start.to_offset()];
+
+        match bio.type_() {
+            BioType::Read => {
+                let status = read_sectors(self.device.as_ref(), sid, &mut buf);
+                if status != BioStatus::Complete {
+                    return status;
+                }
+                self.crypt(sid_range.start, &mut buf, AesXts::decrypt);
+                copy_to_bio(bio, &buf);
+                BioStatus::Complete
+            }
+            BioType::Write => {
+                copy_from_bio(bio, &mut buf);
+                self.crypt(sid_range.start, &mut buf, AesXts::encrypt);
+                write_sectors(self.device.as_ref(), sid, &buf)
+            }
+            BioType::Flush | BioType::Discard => unreachable!(),
+        }
+    }
+
+    /// Encrypts or decrypts the sectors starting from the sector `sid` of the target.
+    fn crypt(
+        &self,
+        sid: Sid,
+        buf: &mut [u8],
+        crypt_fn: fn(&AesXts, u64, &mut [u8], &CipherContext),
+    ) {
+        let first_sector = sid.to_raw().wrapping_add(self.iv_offset);
+        let sectors_per_block = (BLOCK_SIZE / AesXts::DATA_UNIT_SIZE) as u64;
+
+        // The preemption is disabled while the context is alive, so the blocks are encrypted or
+        // decrypted one by one.
+        for (i, block) in buf.chunks_mut(BLOCK_SIZE).enumerate() {
+            let ctx = CipherContext::new();
+            crypt_fn(
+                &self.cipher,
+                first_sector.wrapping_add(i as u64 * sectors_per_block),
+                block,
+                &ctx,
+            );
+        }
+    }
+}
+
+impl BlockDevice for CryptDevice {
+    fn enqueue(&self, bio: SubmittedBio) -> core::result::Result<(), BioEnqueueError> {
+        if matches!(bio.type_(), BioType::Flush | BioType::Discard) {
+            return bio.remap_and_submit(self.device.as_ref(), self.start);
+        }
+
+        // The work function must be `Fn`, so the request is taken out when it is executed.
+        let this = self.weak_self.upgrade().unwrap();
+        let bio = Mutex::new(Some(bio));
+        submit_work_func(
+            move || {
+                let Some(bio) = bio.lock().take() else {
+                    return;
+                };
+                let status = this.handle(&bio);
+                bio.complete(status);
+            },
+            WorkPriority::Normal,
+        );
+
+        Ok(())
+    }
+
+    fn metadata(&self) -> BlockDeviceMeta {
+        BlockDeviceMeta {
+            // The segments are copied into a single segment before they are forwarded.
+            max_nr_segments_per_bio: usize::MAX,
+            nr_sectors: self.len as usize,
+            max_nr_sectors_per_discard: self.device.metadata().max_nr_sectors_per_discard,
+        }
+    }
+}
@@ -0,0 +1,546 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The mapped block devices, i.e., `/dev/dm-<N>` and their aliases `/dev/mapper/<name>`.
+//!
+//! Like the device mapper of Linux, the devices are managed with the ioctls of
+//! `/dev/mapper/control`, which are used by `dmsetup`. A device is created without a table. A
+//! table is loaded as the inactive table, which becomes the active one when the device is resumed.
+//! The `linear`, `striped`, `crypt`, and `verity` targets are supported. For example, the following
+//! commands create a device that concatenates two partitions:
+//!
+//! ```text
+//! dmsetup create concat --table "0 8192 linear /dev/vda1 0
+//! 8192 8192 linear /dev/vda2 0"
+//! ```
+//!
+//! The devices can also be created at boot time with the `dm-mod.create` kernel command-line
+//! argument, whose format is the same as Linux:
+//!
+//! ```text
+//! dm-mod.create="<name>,<uuid>,<minor>,<flags>,<table>[,<table>+][;<name>,...]"
+//! ```
+//!
+//! Where `<flags>` is `ro` or `rw`, and each `<table>` is `<start> <length> <type> <params>`. The
+//! `<minor>` is ignored since the minor numbers are always allocated dynamically.
+//!
+//! Suspending a device only changes its state, which does not hold the I/O requests.
+
+mod bounce;
+mod crypt;
+mod verity;
+
+use aster_block::{
+    dm::{DmError, DmTable, DmTarget, Linear, MappedDevice, Striped, Target},
+    BlockDevice,
+};
+
+use self::{crypt::Crypt, verity::Verity};
+use super::{
+    block::{add_block_alias, add_block_node, delete_block_node, lookup_block_device},
+    *,
+};
+use crate::{
+    current_userspace,
+    events::IoEvents,
+    fs::{device::delete_node, inode_handle::FileIo, utils::IoctlCmd},
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        posix_thread::AsPosixThread,
+        signal::{PollHandle, Pollable},
+    },
+};
+
+/// The major number of the mapped devices, which is in the range of the dynamic majors in Linux.
+const DM_MAJOR: u32 = 253;
+
+/// The version of the ioctl interface, which is the same as Linux.
+const DM_VERSION: [u32; 3] = [4, 48, 0];
+
+/// The maximum size of the data of an ioctl.
+const MAX_DATA_SIZE: usize = 64 * 1024;
+
+crate::kernel_param! {
+    /// The mapped devices that are created at boot time, in the format of
+    /// `<name>,<uuid>,<minor>,<flags>,<table>[,<table>+][;<name>,...]`.
+    static CREATE: &'static str = ("dm-mod.create", "");
+}
+
+static MAPPED_DEVICES: Mutex<BTreeMap<String, MappedDeviceInfo>> = Mutex::new(BTreeMap::new());
+
+pub(super) fn init() -> Result<()> {
+    add_node(Arc::new(DmControl), "mapper/control")?;
+
+    for device in CREATE.get().split(';').filter(|device| !device.is_empty()) {
+        if let Err(err) = create_device_from_kcmd(device) {
+            warn!("failed to create the mapped device `{}`: {:?}", device, err);
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates and activates the device described by `<name>,<uuid>,<minor>,<flags>,<table>[,...]`.
+fn create_device_from_kcmd(desc: &str) -> Result<()> {
+    let mut fields = desc.split(',');
+    let (Some(name), Some(uuid), Some(_minor), Some(flags)) =
+        (fields.next(), fields.next(), fields.next(), fields.next())
+    else {
+        return_errno_with_message!(Errno::EINVAL, "the device description is incomplete");
+    };
+    let read_only = match flags {
+        "ro" => true,
+        "rw" => false,
+        _ => return_errno_with_message!(Errno::EINVAL, "the flags are invalid"),
+    };
+
+    let mut devices = MAPPED_DEVICES.lock();
+    create_device(&mut devices, name, uuid, read_only)?;
+
+    let info = &devices[name];
+    let result = fields
+        .map(|line| {
+            let mut words = line.trim().splitn(4, char::is_whitespace);
+            let (Some(start), Some(len), Some(type_name)) =
+                (words.next(), words.next(), words.next())
+            else {
+                return_errno_with_message!(Errno::EINVAL, "the target is incomplete");
+            };
+            info.parse_target(
+                parse_u64(start)?,
+                parse_u64(len)?,
+                type_name,
+                words.next().unwrap_or(""),
+            )
+        })
+        .collect::<Result<Vec<_>>>()
+        .and_then(|targets| DmTable::new(targets).map_err(Error::from));
+
+    match result {
+        Ok(table) => {
+            info.device.swap_table(Arc::new(table));
+            Ok(())
+        }
+        Err(err) => {
+            remove_device(&mut devices, name)?;
+            Err(err)
+        }
+    }
+}
+
+/// Creates a device named `name` without a table.
+fn create_device(
+    devices: &mut BTreeMap<String, MappedDeviceInfo>,
+    name: &str,
+    uuid: &str,
+    read_only: bool,
+) -> Result<()> {
+    if name.is_empty() || name.len() >= DM_NAME_LEN || name.contains('/') || name == "control" {
+        return_errno_with_message!(Errno::EINVAL, "the device name is invalid");
+    }
+    if uuid.len() >= DM_UUID_LEN {
+        return_errno_with_message!(Errno::EINVAL, "the device UUID is invalid");
+    }
+    if devices.contains_key(name)
+        || (!uuid.is_empty() && devices.values().any(|info| info.uuid == uuid))
+    {
+        return_errno_with_message!(Errno::EBUSY, "the device already exists");
+    }
+
+    // Like Linux, the smallest unused minor number is allocated.
+    let index = (0..)
+        .find(|index| devices.values().all(|info| info.index != *index))
+        .unwrap();
+    let info = MappedDeviceInfo {
+        index,
+        uuid: uuid.to_string(),
+        device: Arc::new(MappedDevice::new(read_only)),
+        inactive_table: None,
+        is_suspended: false,
+    };
+
+    let id = info.id();
+    let dm_name = info.dm_name();
+    aster_block::register_device(dm_name.clone(), info.device.clone());
+    devices.insert(name.to_string(), info);
+
+    let result = add_block_node(&dm_name, id)
+        .and_then(|_| add_block_alias(&format!("mapper/{}", name), &dm_name, id));
+    if let Err(err) = result {
+        remove_device(devices, name)?;
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+/// Removes the device named `name`, which fails with `EBUSY` if the device is in use.
+fn remove_device(devices: &mut BTreeMap<String, MappedDeviceInfo>, name: &str) -> Result<()> {
+    let Some(info) = devices.get(name) else {
+        return_errno_with_message!(Errno::ENXIO, "the device does not exist");
+    };
+    // The device is referenced by the information and the registry of the block devices. Other
+    // references mean that it is mounted, opened, or used by other mapped devices.
+    if Arc::strong_count(&info.device) > 2 {
+        return_errno_with_message!(Errno::EBUSY, "the device is in use");
+    }
+
+    let info = devices.remove(name).unwrap();
+    let dm_name = info.dm_name();
+    aster_block::unregister_device(&dm_name);
+    // The nodes may have been removed by the user.
+    let _ = delete_block_node(&dm_name, info.id());
+    let _ = delete_node(&format!("mapper/{}", name));
+
+    Ok(())
+}
+
+/// The information of a mapped device.
+struct MappedDeviceInfo {
+    /// The index, which is also the minor number
+    index: u32,
+    uuid: String,
+    device: Arc<MappedDevice>,
+    /// The table that is loaded but not activated
+    inactive_table: Option<Arc<DmTable>>,
+    is_suspended: bool,
+}
+
+impl MappedDeviceInfo {
+    fn id(&self) -> DeviceId {
+        DeviceId::new(DM_MAJOR, self.index)
+    }
+
+    /// Returns the name of the device in the registry of the block devices, e.g., `dm-0`.
+    fn dm_name(&self) -> String {
+        format!("dm-{}", self.index)
+    }
+
+    fn parse_target(
+        &self,
+        start: u64,
+        len: u64,
+        type_name: &str,
+        params: &str,
+    ) -> Result<DmTarget> {
+        let lookup = |name: &str| {
+            let device = lookup_block_device(name).ok_or_else(|| {
+                Error::with_message(Errno::ENXIO, "the underlying device does not exist")
+            })?;
+            // The requests would be forwarded to the device itself endlessly.
+            if device
+                .downcast_ref::<MappedDevice>()
+                .is_some_and(|device| core::ptr::eq(device, self.device.as_ref()))
+            {
+                return_errno_with_message!(Errno::EINVAL, "the device cannot map to itself");
+            }
+            Ok(device)
+        };
+
+        let params = params.split_whitespace().collect::<Vec<_>>();
+        let kind: Box<dyn Target> = match (type_name, params.as_slice()) {
+            ("linear", [device, offset]) => {
+                Box::new(Linear::new(lookup(*device)?, parse_u64(offset)?, len)?)
+            }
+            ("striped", [nr_stripes, chunk_sectors, stripes @ ..]) => {
+                if stripes.len() != parse_u64(nr_stripes)? as usize * 2 {
+                    return_errno_with_message!(Errno::EINVAL, "the number of stripes mismatches");
+                }
+                let stripes = stripes
+                    .chunks(2)
+                    .map(|stripe| Ok((lookup(stripe[0])?, parse_u64(stripe[1])?)))
+                    .collect::<Result<Vec<_>>>()?;
+                Box::new(Striped::new(stripes, parse_u64(chunk_sectors)?, len)?)
+            }
+            ("crypt", [cipher, key, iv_offset, device, offset]) => Box::new(Crypt::new(
+                cipher,
+                key,
+                parse_u64(iv_offset)?,
+                lookup(*device)?,
+                parse_u64(offset)?,
+                len,
+            )?),
+            ("verity", ["1", data_device, hash_device, params @ ..]) => Box::new(Verity::new(
+                lookup(data_device)?,
+                lookup(hash_device)?,
+                params,
+                len,
+            )?),
+            ("linear" | "striped" | "crypt" | "verity", _) => {
+                return_errno_with_message!(Errno::EINVAL, "the target parameters are invalid")
+            }
+            _ => return_errno_with_message!(Errno::EINVAL, "the target type is not supported"),
+        };
+
+        Ok(DmTarget::new(start, len, kind)?)
+    }
+
+    fn flags(&self) -> u32 {
+        let mut flags = 0;
+        if self.device.is_read_only() {
+            flags |= DM_READONLY_FLAG;
+        }
+        if self.is_suspended {
+            flags |= DM_SUSPEND_FLAG;
+        }
+        if self.device.table().is_some() {
+            flags |= DM_ACTIVE_PRESENT_FLAG;
+        }
+        if self.inactive_table.is_some() {
+            flags |= DM_INACTIVE_PRESENT_FLAG;
+        }
+        flags
+    }
+
+    /// Fills the status of the device in the header.
+    fn fill_status(&self, name: &str, hdr: &mut DmIoctl) {
+        hdr.flags = self.flags();
+        hdr.dev = self.id().into();
+        hdr.target_count = self
+            .device
+            .table()
+            .map_or(0, |table| table.targets().len() as u32);
+        // The references other than the information and the registry of the block devices.
+        hdr.open_count = Arc::strong_count(&self.device) as i32 - 2;
+        hdr.event_nr = 0;
+        hdr.name = [0; DM_NAME_LEN];
+        hdr.name[..name.len()].copy_from_slice(name.as_bytes());
+        hdr.uuid = [0; DM_UUID_LEN];
+        hdr.uuid[..self.uuid.len()].copy_from_slice(self.uuid.as_bytes());
+    }
+
+    /// Reads the table from the target specifications after the header.
+    fn read_table(&self, hdr: &DmIoctl, arg: usize) -> Result<DmTable> {
+        let data_size = hdr.data_size as usize;
+        if data_size < size_of::<DmIoctl>() || data_size > MAX_DATA_SIZE {
+            return_errno_with_message!(Errno::EINVAL, "the data size is invalid");
+        }
+        let data = current_userspace!().reader(arg, data_size)?.collect()?;
+
+        let mut targets = Vec::with_capacity(hdr.target_count as usize);
+        let mut offset = hdr.data_start as usize;
+        for _ in 0..hdr.target_count {
+            let spec_end = offset + size_of::<DmTargetSpec>();
+            if offset < size_of::<DmIoctl>() || spec_end > data.len() {
+                return_errno_with_message!(Errno::EINVAL, "the target is out of the data");
+            }
+            let spec = DmTargetSpec::from_bytes(&data[offset..spec_end]);
+
+            let type_name = cstr_field(&spec.target_type)?;
+            let params = cstr_field(&data[spec_end..])?;
+            targets.push(self.parse_target(spec.sector_start, spec.length, &type_name, &params)?);
+
+            // The offset of the next specification is relative to the current one.
+            offset += spec.next as usize;
+        }
+
+        Ok(DmTable::new(targets)?)
+    }
+}
+
+fn parse_u64(s: &str) -> Result<u64> {
+    s.parse::<u64>()
+        .map_err(|_| Error::with_message(Errno::EINVAL, "the number is invalid"))
+}
+
+fn parse_hex(s: &str) -> Result<Vec<u8>> {
+    let Some(digits) = s
+        .chars()
+        .map(|c| c.to_digit(16).map(|digit| digit as u8))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return_errno_with_message!(Errno::EINVAL, "the hexadecimal string is invalid");
+    };
+    if digits.len() % 2 != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the hexadecimal string has an odd length");
+    }
+    Ok(digits
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair[1])
+        .collect())
+}
+
+/// The control device of the mapped devices, i.e., `/dev/mapper/control`.
+struct DmControl;
+
+impl DmControl {
+    fn handle_ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<()> {
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        if !credentials.has_capability(CapSet::SYS_ADMIN) {
+            return_errno_with_message!(
+                Errno::EACCES,
+                "the current thread does not have the CAP_SYS_ADMIN capability"
+            );
+        }
+
+        let user_space = current_userspace!();
+        let mut hdr: DmIoctl = user_space.read_val(arg)?;
+        if hdr.version[0] != DM_VERSION[0] {
+            return_errno_with_message!(Errno::EINVAL, "the ioctl version is not supported");
+        }
+        hdr.version = DM_VERSION;
+
+        if let IoctlCmd::DMVERSION = cmd {
+            user_space.write_val(arg, &hdr)?;
+            return Ok(());
+        }
+
+        let name = cstr_field(&hdr.name)?;
+        let uuid = cstr_field(&hdr.uuid)?;
+        let mut devices = MAPPED_DEVICES.lock();
+
+        if let IoctlCmd::DMDEVCREATE = cmd {
+            create_device(
+                &mut devices,
+                &name,
+                &uuid,
+                hdr.flags & DM_READONLY_FLAG != 0,
+            )?;
+            devices[&name].fill_status(&name, &mut hdr);
+            user_space.write_val(arg, &hdr)?;
+            return Ok(());
+        }
+
+        // Like Linux, the device is specified by its name or its UUID.
+        let Some(name) = devices
+            .iter()
+            .find(|(key, info)| {
+                if name.is_empty() {
+                    !uuid.is_empty() && info.uuid == uuid
+                } else {
+                    **key == name
+                }
+            })
+            .map(|(key, _)| key.clone())
+        else {
+            return_errno_with_message!(Errno::ENXIO, "the device does not exist");
+        };
+
+        if let IoctlCmd::DMDEVREMOVE = cmd {
+            return remove_device(&mut devices, &name);
+        }
+
+        let info = devices.get_mut(&name).unwrap();
+        match cmd {
+            IoctlCmd::DMDEVSUSPEND if hdr.flags & DM_SUSPEND_FLAG != 0 => {
+                info.is_suspended = true;
+            }
+            IoctlCmd::DMDEVSUSPEND => {
+                if let Some(table) = info.inactive_table.take() {
+                    info.device.swap_table(table);
+                }
+                info.is_suspended = false;
+            }
+            IoctlCmd::DMDEVSTATUS => {}
+            IoctlCmd::DMTABLELOAD => {
+                let table = info.read_table(&hdr, arg)?;
+                info.inactive_table = Some(Arc::new(table));
+            }
+            IoctlCmd::DMTABLECLEAR => {
+                info.inactive_table = None;
+            }
+            _ => return_errno_with_message!(Errno::ENOTTY, "unsupported ioctl"),
+        }
+
+        info.fill_status(&name, &mut hdr);
+        user_space.write_val(arg, &hdr)?;
+        Ok(())
+    }
+}
+
+/// Returns the string in the NUL-terminated field.
+fn cstr_field(field: &[u8]) -> Result<String> {
+    let Some(len) = field.iter().position(|&byte| byte == 0) else {
+        return_errno_with_message!(Errno::EINVAL, "the string is not NUL-terminated");
+    };
+    core::str::from_utf8(&field[..len])
+        .map(ToString::to_string)
+        .map_err(|_| Error::with_message(Errno::EINVAL, "the string is not valid UTF-8"))
+}
+
+impl Device for DmControl {
+    fn type_(&self) -> DeviceType {
+        DeviceType::MiscDevice
+    }
+
+    fn id(&self) -> DeviceId {
+        // Same value with Linux
+        DeviceId::new(10, 236)
+    }
+}
+
+impl Pollable for DmControl {
+    fn poll(&self, _mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        IoEvents::empty()
+    }
+}
+
+impl FileIo for DmControl {
+    fn read(&self, _writer: &mut VmWriter) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "the control device cannot be read");
+    }
+
+    fn write(&self, _reader: &mut VmReader) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "the control device cannot be written");
+    }
+
+    fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
+        self.handle_ioctl(cmd, arg)?;
+        Ok(0)
+    }
+}
+
+impl From<DmError> for Error {
+    fn from(err: DmError) -> Self {
+        match err {
+            DmError::InvalidTable => Error::with_message(Errno::EINVAL, "the table is invalid"),
+            DmError::InvalidArgs => {
+                Error::with_message(Errno::EINVAL, "the target parameters are invalid")
+            }
+            DmError::DeviceTooSmall => {
+                Error::with_message(Errno::EINVAL, "the underlying device is too small")
+            }
+        }
+    }
+}
+
+const DM_NAME_LEN: usize = 128;
+const DM_UUID_LEN: usize = 129;
+
+const DM_READONLY_FLAG: u32 = 1 << 0;
+const DM_SUSPEND_FLAG: u32 = 1 << 1;
+const DM_ACTIVE_PRESENT_FLAG: u32 = 1 << 5;
+const DM_INACTIVE_PRESENT_FLAG: u32 = 1 << 6;
+
+/// The header of the ioctls, i.e., `struct dm_ioctl` in Linux.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct DmIoctl {
+    version: [u32; 3],
+    /// The size of the header and the data after it
+    data_size: u32,
+    /// The offset of the data from the start of the header
+    data_start: u32,
+    target_count: u32,
+    open_count: i32,
+    flags: u32,
+    event_nr: u32,
+    _padding: u32,
+    dev: u64,
+    name: [u8; DM_NAME_LEN],
+    uuid: [u8; DM_UUID_LEN],
+    _data: [u8; 7],
+}
+
+/// The specification of a target, i.e., `struct dm_target_spec` in Linux, which is followed by
+/// the NUL-terminated parameters.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct DmTargetSpec {
+    sector_start: u64,
+    length: u64,
+    status: i32,
+    /// The offset of the next specification from the start of this one
+    next: u32,
+    target_type: [u8; 16],
+}
@@ -0,0 +1,416 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `verity` target, which verifies the integrity of the sectors with a hash tree like
+//! `dm-verity` of Linux.
+//!
+//! The parameters of the target and the format of the hash tree are the same as Linux (i.e., the
+//! ones generated by `veritysetup format`):
+//!
+//! ```text
+//! <version> <data_dev> <hash_dev> <data_block_size> <hash_block_size> <num_data_blocks>
+//! <hash_start_block> <algorithm> <root_digest> <salt>
+//! ```
+//!
+//! Only the version 1 and the `sha256` algorithm are supported, and the `<salt>` is `-` if there
+//! is no salt. A data block is read only if it matches the hash tree, whose root digest is given
+//! by the table. Otherwise, the read request fails. The target is read-only.
+//!
+//! To verify the root file system at boot time, the table can be given by the `dm-mod.create`
+//! kernel command-line argument, which is measured along with the kernel command line. In TDX
+//! guests, the root digest is also extended into the RTMR 3 when the table is loaded, where the
+//! measurement is the root digest followed by 16 zero bytes.
+
+use core::{num::NonZeroUsize, ops::Range};
+
+use aster_block::{
+    bio::{BioEnqueueError, BioStatus, BioType, SubmittedBio},
+    dm::{DmError, Target, TargetPiece},
+    id::Sid,
+    BlockDevice, BlockDeviceMeta, SECTOR_SIZE,
+};
+use lru::LruCache;
+
+use super::{
+    bounce::{copy_to_bio, read_sectors},
+    parse_hex, parse_u64,
+};
+use crate::{
+    crypto::Sha256,
+    prelude::*,
+    thread::work_queue::{submit_work_func, WorkPriority},
+};
+
+/// The maximum number of the verified hash blocks that are cached.
+const HASH_CACHE_SIZE: usize = 256;
+
+/// The target that verifies its sectors, which are the consecutive sectors from the start of a
+/// device.
+#[derive(Debug)]
+pub(super) struct Verity {
+    device: Arc<VerityDevice>,
+}
+
+impl Verity {
+    /// Creates a target of `len` sectors from the parameters after the devices.
+    ///
+    /// The `params` are `<data_block_size> <hash_block_size> <num_data_blocks>
+    /// <hash_start_block> <algorithm> <root_digest> <salt>`.
+    pub(super) fn new(
+        data_device: Arc<dyn BlockDevice>,
+        hash_device: Arc<dyn BlockDevice>,
+        params: &[&str],
+        len: u64,
+    ) -> Result<Self> {
+        let [data_block_size, hash_block_size, nr_blocks, hash_start, algorithm, root, salt] =
+            params
+        else {
+            return_errno_with_message!(Errno::EINVAL, "the verity parameters are invalid");
+        };
+
+        let data_block_size = parse_block_size(data_block_size)?;
+        let hash_block_size = parse_block_size(hash_block_size)?;
+        let nr_data_blocks = parse_u64(nr_blocks)?;
+        let hash_start = parse_u64(hash_start)?;
+        if *algorithm != "sha256" {
+            return_errno_with_message!(Errno::EINVAL, "the hash algorithm is not supported");
+        }
+        let root_digest: [u8; Sha256::DIGEST_SIZE] = parse_hex(root)?
+            .try_into()
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the root digest size is invalid"))?;
+        let salt = if *salt == "-" {
+            Vec::new()
+        } else {
+            parse_hex(salt)?
+        };
+
+        let tree = HashTree::new(
+            nr_data_blocks,
+            data_block_size,
+            hash_block_size,
+            hash_start,
+            root_digest,
+            salt,
+        )?;
+        let data_sectors = nr_data_blocks.checked_mul(tree.sectors_per_data_block());
+        if data_sectors.is_none_or(|data_sectors| {
+            len > data_sectors || data_sectors > data_device.metadata().nr_sectors as u64
+        }) {
+            return Err(DmError::DeviceTooSmall.into());
+        }
+        if tree.end_sector() > hash_device.metadata().nr_sectors as u64 {
+            return Err(DmError::DeviceTooSmall.into());
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        ostd::if_tdx_enabled!({
+            measure_root_digest(&root_digest)?;
+        });
+
+        let device = Arc::new_cyclic(|weak_self| VerityDevice {
+            weak_self: weak_self.clone(),
+            data_device,
+            hash_device,
+            tree,
+            hash_cache: Mutex::new(LruCache::new(NonZeroUsize::new(HASH_CACHE_SIZE).unwrap())),
+            len,
+        });
+        Ok(Self { device })
+    }
+}
+
+impl Target for Verity {
+    fn type_name(&self) -> &'static str {
+        "verity"
+    }
+
+    fn devices(&self) -> Vec<Arc<dyn BlockDevice>> {
+        vec![self.device.clone() as Arc<dyn BlockDevice>]
+    }
+
+    fn map(&self, sid_range: Range<u64>, pieces: &mut Vec<TargetPiece>) {
+        // The sectors are sent to the internal device, which verifies them after reading them
+        // from the data device.
+        pieces.push(TargetPiece {
+            device: self.device.clone(),
+            sid_range: Sid::new(sid_range.start)..Sid::new(sid_range.end),
+            target_sid: Sid::new(sid_range.start),
+        });
+    }
+}
+
+/// Extends the root digest into the RTMR for the runtime measurements.
+#[cfg(all(target_arch = "x86_64", feature = "cvm_guest"))]
+fn measure_root_digest(root_digest: &[u8; Sha256::DIGEST_SIZE]) -> Result<()> {
+    use super::super::tdxguest::{extend_runtime_measurement, TDX_MEASUREMENT_LEN};
+
+    const RTMR_INDEX: u64 = 3;
+
+    let mut measurement = [0; TDX_MEASUREMENT_LEN];
+    measurement[..Sha256::DIGEST_SIZE].copy_from_slice(root_digest);
+    extend_runtime_measurement(RTMR_INDEX, &measurement)
+}
+
+/// The geometry of a hash tree.
+///
+/// The level 0 of the tree consists of the digests of the data blocks, and each higher level
+/// consists of the digests of the hash blocks in the lower level. The highest level has only one
+/// hash block, whose digest is the root digest. The levels are stored on the hash device from the
+/// highest to the lowest.
+#[derive(Debug)]
+struct HashTree {
+    data_block_size: usize,
+    hash_block_size: usize,
+    /// The number of bits of the number of digests in a hash block
+    digests_per_block_bits: u32,
+    /// The first hash block of each level, where the hash blocks are counted from the start of
+    /// the hash device
+    level_starts: Vec<u64>,
+    /// The hash block after the last one
+    end: u64,
+    root_digest: [u8; Sha256::DIGEST_SIZE],
+    salt: Vec<u8>,
+}
+
+impl HashTree {
+    fn new(
+        nr_data_blocks: u64,
+        data_block_size: usize,
+        hash_block_size: usize,
+        hash_start: u64,
+        root_digest: [u8; Sha256::DIGEST_SIZE],
+        salt: Vec<u8>,
+    ) -> Result<Self> {
+        let digests_per_block_bits = (hash_block_size / Sha256::DIGEST_SIZE).ilog2();
+
+        let mut nr_levels = 0;
+        while nr_levels * digests_per_block_bits < u64::BITS
+            && (nr_data_blocks.saturating_sub(1) >> (nr_levels * digests_per_block_bits)) != 0
+        {
+            nr_levels += 1;
+        }
+
+        let mut level_starts = vec![0; nr_levels as usize];
+        let mut next_start = hash_start;
+        for (level, start) in level_starts.iter_mut().enumerate().rev() {
+            // The number of hash blocks in the level, which are rounded up.
+            let shift = (level as u32 + 1) * digests_per_block_bits;
+            let nr_blocks = (nr_data_blocks as u128).div_ceil(1 << shift) as u64;
+
+            *start = next_start;
+            next_start = next_start
+                .checked_add(nr_blocks)
+                .ok_or_else(|| Error::from(DmError::DeviceTooSmall))?;
+        }
+
+        let tree = Self {
+            data_block_size,
+            hash_block_size,
+            digests_per_block_bits,
+            level_starts,
+            end: next_start,
+            root_digest,
+            salt,
+        };
+        if tree
+            .end
+            .checked_mul(tree.sectors_per_hash_block())
+            .is_none()
+        {
+            return Err(DmError::DeviceTooSmall.into());
+        }
+        Ok(tree)
+    }
+
+    fn sectors_per_data_block(&self) -> u64 {
+        (self.data_block_size / SECTOR_SIZE) as u64
+    }
+
+    fn sectors_per_hash_block(&self) -> u64 {
+        (self.hash_block_size / SECTOR_SIZE) as u64
+    }
+
+    /// Returns the sector after the last hash block.
+    fn end_sector(&self) -> u64 {
+        self.end * self.sectors_per_hash_block()
+    }
+
+    /// Returns the digest of the data block or the hash block.
+    fn digest(&self, block: &[u8]) -> [u8; Sha256::DIGEST_SIZE] {
+        // The salt is prepended in the version 1.
+        let mut hasher = Sha256::new();
+        hasher.update(&self.salt);
+        hasher.update(block);
+        hasher.finish()
+    }
+
+    /// Returns the position of the digest of the `index`-th block below the level.
+    ///
+    /// The position consists of the hash block on the hash device and the offset within it.
+    fn digest_position(&self, level: usize, index: u64) -> (u64, usize) {
+        let hash_block = self.level_starts[level] + (index >> self.digests_per_block_bits);
+        let digest_index = (index & ((1 << self.digests_per_block_bits) - 1)) as usize;
+        // The digests are padded to the power of two in the version 1.
+        let offset = digest_index * (self.hash_block_size >> self.digests_per_block_bits);
+        (hash_block, offset)
+    }
+}
+
+/// The internal device of a `verity` target, whose sectors are the sectors of the target.
+///
+/// The requests are handled in the work queue, since they must wait for the requests to the
+/// underlying devices before they are verified.
+#[derive(Debug)]
+struct VerityDevice {
+    weak_self: Weak<Self>,
+    data_device: Arc<dyn BlockDevice>,
+    hash_device: Arc<dyn BlockDevice>,
+    tree: HashTree,
+    /// The hash blocks that have been verified, indexed by their positions on the hash device
+    hash_cache: Mutex<LruCache<u64, Arc<[u8]>>>,
+    len: u64,
+}
+
+impl VerityDevice {
+    fn handle_read(&self, bio: &SubmittedBio) -> BioStatus {
+        let sectors_per_block = self.tree.sectors_per_data_block();
+        let sid_range = bio.sid_range();
+        let first_block = sid_range.start.to_raw() / sectors_per_block;
+        let end_block = sid_range.end.to_raw().div_ceil(sectors_per_block);
+
+        // The whole data blocks are read since they are verified as a whole.
+        let mut buf = vec![0u8; (end_block - first_block) as usize * self.tree.data_block_size];
+        let status = read_sectors(
+            self.data_device.as_ref(),
+            Sid::new(first_block * sectors_per_block),
+            &mut buf,
+        );
+        if status != BioStatus::Complete {
+            return status;
+        }
+
+        for (i, block) in buf.chunks(self.tree.data_block_size).enumerate() {
+            let index = first_block + i as u64;
+            let digest = self.tree.digest(block);
+            if let Err(status) = self.check_digest(0, index, &digest) {
+                warn!("the data block {} of the verity target is corrupted", index);
+                return status;
+            }
+        }
+
+        let offset = (sid_range.start.to_raw() - first_block * sectors_per_block) as usize;
+        copy_to_bio(bio, &buf[offset * SECTOR_SIZE..]);
+        BioStatus::Complete
+    }
+
+    /// Checks the digest of the `index`-th block below the level.
+    ///
+    /// The blocks below the level 0 are the data blocks, and the block below the highest level
+    /// is the hash block of the highest level.
+    fn check_digest(
+        &self,
+        level: usize,
+        index: u64,
+        digest: &[u8; Sha256::DIGEST_SIZE],
+    ) -> core::result::Result<(), BioStatus> {
+        let is_matched = if level == self.tree.level_starts.len() {
+            *digest == self.tree.root_digest
+        } else {
+            let (hash_block, offset) = self.tree.digest_position(level, index);
+            let hash_block = self.verified_hash_block(level, hash_block)?;
+            hash_block[offset..offset + Sha256::DIGEST_SIZE] == digest[..]
+        };
+
+        if is_matched {
+            Ok(())
+        } else {
+            Err(BioStatus::IoError)
+        }
+    }
+
+    /// Reads and verifies the hash block in the level.
+    fn verified_hash_block(
+        &self,
+        level: usize,
+        hash_block: u64,
+    ) -> core::result::Result<Arc<[u8]>, BioStatus> {
+        if let Some(block) = self.hash_cache.lock().get(&hash_block) {
+            return Ok(block.clone());
+        }
+
+        let mut block = vec![0u8; self.tree.hash_block_size];
+        let sectors_per_block = self.tree.sectors_per_hash_block();
+        let status = read_sectors(
+            self.hash_device.as_ref(),
+            Sid::new(hash_block * sectors_per_block),
+            &mut block,
+        );
+        if status != BioStatus::Complete {
+            return Err(status);
+        }
+
+        let digest = self.tree.digest(&block);
+        let index = hash_block - self.tree.level_starts[level];
+        if let Err(status) = self.check_digest(level + 1, index, &digest) {
+            warn!(
+                "the hash block {} of the verity target is corrupted",
+                hash_block
+            );
+            return Err(status);
+        }
+
+        let block: Arc<[u8]> = block.into();
+        self.hash_cache.lock().put(hash_block, block.clone());
+        Ok(block)
+    }
+}
+
+impl BlockDevice for VerityDevice {
+    fn enqueue(&self, bio: SubmittedBio) -> core::result::Result<(), BioEnqueueError> {
+        match bio.type_() {
+            BioType::Read => (),
+            // There is nothing to flush since the target is read-only.
+            BioType::Flush => {
+                bio.complete(BioStatus::Complete);
+                return Ok(());
+            }
+            BioType::Write | BioType::Discard => {
+                bio.complete(BioStatus::IoError);
+                return Ok(());
+            }
+        }
+
+        // The work function must be `Fn`, so the request is taken out when it is executed.
+        let this = self.weak_self.upgrade().unwrap();
+        let bio = Mutex::new(Some(bio));
+        submit_work_func(
+            move || {
+                let Some(bio) = bio.lock().take() else {
+                    return;
+                };
+                let status = this.handle_read(&bio);
+                bio.complete(status);
+            },
+            WorkPriority::Normal,
+        );
+
+        Ok(())
+    }
+
+    fn metadata(&self) -> BlockDeviceMeta {
+        BlockDeviceMeta {
+            // The segments are filled from a single buffer.
+            max_nr_segments_per_bio: usize::MAX,
+            nr_sectors: self.len as usize,
+            max_nr_sectors_per_discard: 0,
+        }
+    }
+}
+
+/// Parses the size of a block, which must be a power of two between a sector and a page.
+fn parse_block_size(s: &str) -> Result<usize> {
+    let size = parse_u64(s)? as usize;
+    if !size.is_power_of_two() || !(SECTOR_SIZE..=PAGE_SIZE).contains(&size) {
+        return_errno_with_message!(Errno::EINVAL, "the block size is invalid");
+    }
+    Ok(size)
+}
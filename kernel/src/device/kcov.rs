@@ -0,0 +1,185 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The kcov device, i.e., `/sys/kernel/debug/kcov`, which collects the coverage of the kernel
+//! code executed by a thread.
+//!
+//! The interface is the same as Linux, so that coverage-guided fuzzers like syzkaller can use
+//! it without modification:
+//! 1. `KCOV_INIT_TRACE` sets the number of the words in the trace;
+//! 2. `mmap` maps the trace to the user space;
+//! 3. `KCOV_ENABLE` starts recording the PCs of the kernel code executed by the calling thread
+//!    into the trace, where the first word is the number of the recorded PCs;
+//! 4. `KCOV_DISABLE` stops recording. The recording also stops when the thread exits.
+//!
+//! Each opened file has its own trace, which can be enabled for one thread at a time. Only the
+//! PCs are recorded (i.e., `KCOV_TRACE_PC`). The comparison operands (i.e., `KCOV_TRACE_CMP`)
+//! and the remote coverage are not supported.
+//!
+//! The PCs are recorded only if the kernel is built by `cargo osdk build --kcov`. Otherwise,
+//! the device works but the trace stays empty. See [`ostd::coverage`] for the details.
+
+use align_ext::AlignExt;
+use aster_rights::Rights;
+use ostd::coverage::PcTrace;
+
+use super::*;
+use crate::{
+    events::IoEvents,
+    fs::{
+        fs_resolver::{FsPath, FsResolver},
+        inode_handle::FileIo,
+        utils::{InodeMode, IoctlCmd},
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+    vm::vmo::{CommitFlags, Vmo, VmoOptions},
+};
+
+/// The mode that records the PCs.
+const KCOV_TRACE_PC: usize = 0;
+/// The mode that records the comparison operands.
+const KCOV_TRACE_CMP: usize = 1;
+
+/// The maximum number of the words in a trace, which is the same as Linux.
+const MAX_TRACE_WORDS: usize = i32::MAX as usize / size_of::<usize>();
+
+pub(super) fn init() -> Result<()> {
+    let debug_dentry = FsResolver::new().lookup(&FsPath::try_from("/sys/kernel/debug")?)?;
+    let kcov: Arc<dyn Device> = Arc::new(Kcov);
+    debug_dentry.mknod("kcov", InodeMode::from_bits_truncate(0o600), kcov.into())?;
+
+    Ok(())
+}
+
+struct Kcov;
+
+impl Device for Kcov {
+    fn type_(&self) -> DeviceType {
+        DeviceType::MiscDevice
+    }
+
+    fn id(&self) -> DeviceId {
+        // Linux creates the file in debugfs without a device number. This is a dynamic minor
+        // number of the miscellaneous devices.
+        DeviceId::new(10, 124)
+    }
+
+    fn open(&self) -> Result<Option<Arc<dyn FileIo>>> {
+        Ok(Some(Arc::new(KcovFile {
+            trace: Mutex::new(None),
+        })))
+    }
+}
+
+/// An opened kcov device.
+struct KcovFile {
+    /// The trace and the VMO that maps it, which are set by `KCOV_INIT_TRACE`.
+    trace: Mutex<Option<(Arc<PcTrace>, Vmo<Rights>)>>,
+}
+
+impl KcovFile {
+    fn init_trace(&self, nr_words: usize) -> Result<()> {
+        if !(2..=MAX_TRACE_WORDS).contains(&nr_words) {
+            return_errno_with_message!(Errno::EINVAL, "the trace size is invalid");
+        }
+
+        let mut trace = self.trace.lock();
+        if trace.is_some() {
+            return_errno_with_message!(Errno::EBUSY, "the trace is already initialized");
+        }
+
+        let size = (nr_words * size_of::<usize>()).align_up(PAGE_SIZE);
+        let vmo = VmoOptions::<Rights>::new(size).alloc()?;
+        // The pages are committed in advance, since the PCs are recorded without page faults.
+        let frames = (0..size / PAGE_SIZE)
+            .map(|page_idx| vmo.commit_on(page_idx, CommitFlags::empty()))
+            .collect::<Result<_>>()?;
+        *trace = Some((PcTrace::new(frames, nr_words), vmo));
+
+        Ok(())
+    }
+
+    fn enable(&self, mode: usize) -> Result<()> {
+        match mode {
+            KCOV_TRACE_PC => (),
+            KCOV_TRACE_CMP => {
+                return_errno_with_message!(
+                    Errno::EOPNOTSUPP,
+                    "the comparison operands are not supported"
+                )
+            }
+            _ => return_errno_with_message!(Errno::EINVAL, "the trace mode is invalid"),
+        }
+
+        let trace = self.trace.lock();
+        let Some((trace, _)) = trace.as_ref() else {
+            return_errno_with_message!(Errno::EINVAL, "the trace is not initialized");
+        };
+        if trace.enable().is_err() {
+            return_errno_with_message!(
+                Errno::EBUSY,
+                "the trace or the current thread is already being traced"
+            );
+        }
+
+        Ok(())
+    }
+
+    fn disable(&self, arg: usize) -> Result<()> {
+        if arg != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the argument must be zero");
+        }
+
+        let trace = self.trace.lock();
+        let Some((trace, _)) = trace.as_ref() else {
+            return_errno_with_message!(Errno::EINVAL, "the trace is not initialized");
+        };
+        if trace.disable().is_err() {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the trace is not enabled for the current thread"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl Pollable for KcovFile {
+    fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        let events = IoEvents::IN | IoEvents::OUT;
+        events & mask
+    }
+}
+
+impl FileIo for KcovFile {
+    fn read(&self, _writer: &mut VmWriter) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "the trace can only be accessed via mmap");
+    }
+
+    fn write(&self, _reader: &mut VmReader) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "the trace can only be accessed via mmap");
+    }
+
+    fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
+        match cmd {
+            IoctlCmd::KCOVINITTRACE => self.init_trace(arg)?,
+            IoctlCmd::KCOVENABLE => self.enable(arg)?,
+            IoctlCmd::KCOVDISABLE => self.disable(arg)?,
+            _ => return_errno_with_message!(Errno::ENOTTY, "unsupported ioctl"),
+        }
+        Ok(0)
+    }
+
+    fn mmap_vmo(&self, offset: usize, len: usize) -> Result<(Vmo, usize)> {
+        let trace = self.trace.lock();
+        let Some((_, vmo)) = trace.as_ref() else {
+            return_errno_with_message!(Errno::EINVAL, "the trace is not initialized");
+        };
+        if offset != 0 || len > vmo.size() {
+            return_errno_with_message!(Errno::EINVAL, "the mapping is out of the trace");
+        }
+
+        Ok((vmo.dup()?, 0))
+    }
+}
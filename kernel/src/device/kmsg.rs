@@ -0,0 +1,254 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `/dev/kmsg` device, with which the user space reads and writes the kernel log.
+//!
+//! Each write is a message, which is printed on the console in the same stream as the messages
+//! of the kernel, e.g., the reports of the missing system calls, if its level is below the console
+//! log level. This allows the test harnesses to report results that the host collects from the
+//! console. The message is also appended to
+//! the kernel log as a record. Like Linux, an optional priority prefix (e.g., `<6>`) is removed
+//! and used as the priority of the record.
+//!
+//! Each read returns exactly one record in the same format as Linux:
+//! ```text
+//! <priority>,<sequence number>,<timestamp in microseconds>,-;<message>
+//! ```
+//! Every opened file has its own position in the kernel log, which starts at the oldest record
+//! and can be changed with `lseek`:
+//! - `SEEK_SET` moves to the oldest record;
+//! - `SEEK_DATA` moves to the oldest record that is not cleared (e.g., by `syslog(2)`);
+//! - `SEEK_END` moves after the newest record.
+//!
+//! If the records at the position have been overwritten, the read fails with `EPIPE` and the
+//! position moves to the oldest record.
+//!
+//! The console log level can be specified with the `klog.console_level=<level>` kernel
+//! command-line argument, and changed at runtime with `syslog(2)`.
+
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use aster_logger::{
+    append_record, first_record_seq, first_uncleared_record_seq, next_record_seq, print_at_level,
+    read_record, set_console_level, DEFAULT_CONSOLE_LEVEL, MAX_RECORD_LEN,
+};
+use spin::Once;
+
+use super::*;
+use crate::{
+    events::IoEvents,
+    fs::{inode_handle::FileIo, utils::SeekFrom},
+    prelude::*,
+    process::signal::{PollHandle, Pollable, Pollee},
+};
+
+/// The maximum length of a message, which is the same as Linux.
+const MAX_MSG_LEN: usize = 1024;
+
+/// The syslog priority of the messages without a priority prefix, i.e., `LOG_USER | LOG_WARNING`
+/// as in Linux.
+const DEFAULT_PRIORITY: u8 = (1 << 3) | 4;
+
+crate::kernel_param! {
+    /// The console log level, below which the levels of the messages are printed on the console.
+    static CONSOLE_LEVEL: u8 = ("klog.console_level", DEFAULT_CONSOLE_LEVEL);
+}
+
+/// The pollee that is notified when new records are added.
+///
+/// The kernel log may be appended in any context, including the ones that cannot wake up other
+/// threads. So the new records are checked in the timer interrupt instead.
+static RECORD_POLLEE: Once<Pollee> = Once::new();
+
+pub(super) fn init() {
+    RECORD_POLLEE.call_once(Pollee::new);
+
+    set_console_level(CONSOLE_LEVEL.get());
+
+    static LAST_SEQ: AtomicU64 = AtomicU64::new(0);
+    ostd::timer::register_callback(|| {
+        let next_seq = next_record_seq();
+        if LAST_SEQ.swap(next_seq, Ordering::Relaxed) != next_seq {
+            RECORD_POLLEE.get().unwrap().notify(IoEvents::IN);
+        }
+    });
+}
+
+/// Returns the pollee that is notified when new records are added.
+pub fn kernel_log_pollee() -> &'static Pollee {
+    RECORD_POLLEE.get().unwrap()
+}
+
+pub struct Kmsg;
+
+impl Device for Kmsg {
+    fn type_(&self) -> DeviceType {
+        DeviceType::CharDevice
+    }
+
+    fn id(&self) -> DeviceId {
+        // Same value with Linux
+        DeviceId::new(1, 11)
+    }
+
+    fn open(&self) -> Result<Option<Arc<dyn FileIo>>> {
+        Ok(Some(Arc::new(KmsgFile {
+            seq: Mutex::new(first_record_seq()),
+        })))
+    }
+}
+
+impl Pollable for Kmsg {
+    fn poll(&self, _mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        IoEvents::empty()
+    }
+}
+
+impl FileIo for Kmsg {
+    fn read(&self, _writer: &mut VmWriter) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "cannot read the kmsg device");
+    }
+
+    fn write(&self, _reader: &mut VmReader) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "cannot write the kmsg device");
+    }
+}
+
+/// An opened `/dev/kmsg`.
+struct KmsgFile {
+    /// The sequence number of the next record to read.
+    seq: Mutex<u64>,
+}
+
+impl KmsgFile {
+    fn try_read(&self, writer: &mut VmWriter) -> Result<usize> {
+        let mut seq = self.seq.lock();
+
+        let mut text = vec![0; MAX_RECORD_LEN];
+        let Some(info) = read_record(*seq, &mut text) else {
+            return_errno_with_message!(Errno::EAGAIN, "there are no new records");
+        };
+        if info.seq != *seq {
+            *seq = info.seq;
+            return_errno_with_message!(Errno::EPIPE, "the records have been overwritten");
+        }
+
+        let mut line = format!(
+            "{},{},{},-;",
+            info.priority,
+            info.seq,
+            info.timestamp.as_micros()
+        );
+        // Like Linux, the non-printable characters are escaped so that a record is one line.
+        for &byte in &text[..info.len] {
+            if byte < b' ' || byte >= 0x7f || byte == b'\\' {
+                let _ = write!(line, "\\x{:02x}", byte);
+            } else {
+                line.push(byte as char);
+            }
+        }
+        line.push('\n');
+
+        if line.len() > writer.avail() {
+            return_errno_with_message!(Errno::EINVAL, "the buffer is too small for the record");
+        }
+        writer.write_fallible(&mut line.as_bytes().into())?;
+
+        *seq += 1;
+        Ok(line.len())
+    }
+}
+
+impl Pollable for KmsgFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        // The events depend on the position of each opened file, so they cannot be cached in the
+        // shared pollee.
+        if let Some(poller) = poller {
+            RECORD_POLLEE.get().unwrap().register_poller(poller, mask);
+        }
+
+        let mut events = IoEvents::OUT;
+        if *self.seq.lock() < next_record_seq() {
+            events |= IoEvents::IN;
+        }
+        events & mask
+    }
+}
+
+impl FileIo for KmsgFile {
+    fn read(&self, writer: &mut VmWriter) -> Result<usize> {
+        self.wait_events(IoEvents::IN, None, || self.try_read(writer))
+    }
+
+    fn write(&self, reader: &mut VmReader) -> Result<usize> {
+        if reader.remain() > MAX_MSG_LEN {
+            return_errno_with_message!(Errno::EINVAL, "the message is too long");
+        }
+
+        let buf = reader.collect()?;
+        let msg = String::from_utf8_lossy(&buf);
+        let (priority, text) = parse_priority(&msg);
+        for line in text.lines() {
+            print_at_level(Some(priority & 0x7), format_args!("{}\n", line));
+        }
+
+        append_record(priority, text.trim_end_matches('\n').as_bytes());
+        RECORD_POLLEE.get().unwrap().notify(IoEvents::IN);
+
+        Ok(buf.len())
+    }
+
+    fn seek(&self, pos: SeekFrom) -> Option<Result<usize>> {
+        let is_zero_offset = matches!(
+            pos,
+            SeekFrom::Start(0)
+                | SeekFrom::End(0)
+                | SeekFrom::Current(0)
+                | SeekFrom::Data(0)
+                | SeekFrom::Hole(0)
+        );
+        if !is_zero_offset {
+            return Some(Err(Error::with_message(
+                Errno::ESPIPE,
+                "the kernel log can only be sought with a zero offset",
+            )));
+        }
+
+        let seq = match pos {
+            SeekFrom::Start(_) => first_record_seq(),
+            SeekFrom::Data(_) => first_uncleared_record_seq(),
+            SeekFrom::End(_) => next_record_seq(),
+            SeekFrom::Current(_) | SeekFrom::Hole(_) => {
+                return Some(Err(Error::with_message(
+                    Errno::EINVAL,
+                    "the kernel log cannot be sought from this position",
+                )));
+            }
+        };
+
+        *self.seq.lock() = seq;
+        Some(Ok(0))
+    }
+}
+
+/// Parses and removes the priority prefix, e.g., `<6>`, of the message.
+///
+/// As in Linux, the facility is `LOG_USER` unless it is specified in the prefix.
+fn parse_priority(msg: &str) -> (u8, &str) {
+    let Some((priority, text)) = msg
+        .strip_prefix('<')
+        .and_then(|rest| rest.split_once('>'))
+        .and_then(|(priority, text)| Some((priority.parse::<u32>().ok()?, text)))
+    else {
+        return (DEFAULT_PRIORITY, msg);
+    };
+
+    let level = (priority & 0x7) as u8;
+    let facility = match (priority >> 3) & 0x1f {
+        0 => DEFAULT_PRIORITY >> 3,
+        facility => facility as u8,
+    };
+    ((facility << 3) | level, text)
+}
@@ -1,23 +1,35 @@
 // SPDX-License-Identifier: MPL-2.0
 
+mod block;
+mod dm;
+mod kcov;
+mod kmsg;
 mod null;
 mod pty;
 mod random;
+mod sg;
 mod shm;
+mod snd;
 pub mod tty;
+mod unknown;
 mod urandom;
 mod zero;
 
 #[cfg(all(target_arch = "x86_64", feature = "cvm_guest"))]
 mod tdxguest;
 
+pub use block::get_block_device;
+pub use kmsg::kernel_log_pollee;
 pub use pty::{new_pty_pair, PtyMaster, PtySlave};
 pub use random::Random;
 pub use urandom::Urandom;
 
-use self::tty::get_n_tty;
+use self::{tty::get_n_tty, unknown::UnknownDevice};
 use crate::{
-    fs::device::{add_node, Device, DeviceId, DeviceType},
+    fs::{
+        device::{add_node, Device, DeviceId, DeviceType},
+        utils::InodeType,
+    },
     prelude::*,
 };
 
@@ -40,16 +52,34 @@ pub fn init() -> Result<()> {
     add_node(random, "random")?;
     let urandom = Arc::new(urandom::Urandom);
     add_node(urandom, "urandom")?;
+    kmsg::init();
+    let kmsg = Arc::new(kmsg::Kmsg);
+    add_node(kmsg, "kmsg")?;
     pty::init()?;
     shm::init()?;
+    snd::init()?;
+    sg::init()?;
+    kcov::init()?;
     Ok(())
 }
 
+/// Init the device nodes of the block devices, must be called after the requests of the block
+/// devices can be handled.
+pub fn lazy_init() -> Result<()> {
+    block::init()?;
+    dm::init()
+}
+
+/// Gets the device of a device node with the type and the device number.
+///
+/// Like Linux, a device node can be created even if no device has the device number. In this case,
+/// an [`UnknownDevice`] that records the device number is returned.
+//
 // TODO: Implement a more scalable solution for ID-to-device mapping.
 // Instead of hardcoding every device numbers in this function,
 // a registration mechanism should be used to allow each driver to
 // allocate device IDs either statically or dynamically.
-pub fn get_device(dev: usize) -> Result<Arc<dyn Device>> {
+pub fn get_device(type_: DeviceType, dev: usize) -> Result<Arc<dyn Device>> {
     if dev == 0 {
         return_errno_with_message!(Errno::EPERM, "whiteout device")
     }
@@ -58,12 +88,18 @@ pub fn get_device(dev: usize) -> Result<Arc<dyn Device>> {
     let major = devid.major();
     let minor = devid.minor();
 
-    match (major, minor) {
-        (1, 3) => Ok(Arc::new(null::Null)),
-        (1, 5) => Ok(Arc::new(zero::Zero)),
-        (5, 0) => Ok(Arc::new(tty::TtyDevice)),
-        (1, 8) => Ok(Arc::new(random::Random)),
-        (1, 9) => Ok(Arc::new(urandom::Urandom)),
-        _ => return_errno_with_message!(Errno::EINVAL, "unsupported device"),
-    }
+    let device: Option<Arc<dyn Device>> = match (major, minor) {
+        (1, 3) => Some(Arc::new(null::Null)),
+        (1, 5) => Some(Arc::new(zero::Zero)),
+        (5, 0) => Some(Arc::new(tty::TtyDevice)),
+        (1, 8) => Some(Arc::new(random::Random)),
+        (1, 9) => Some(Arc::new(urandom::Urandom)),
+        (1, 11) => Some(Arc::new(kmsg::Kmsg)),
+        _ => block::get_node(devid),
+    };
+
+    let device = device
+        .filter(|device| InodeType::from(device.type_()) == InodeType::from(type_))
+        .unwrap_or_else(|| Arc::new(UnknownDevice::new(type_, devid)));
+    Ok(device)
 }
@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use alloc::format;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 use ostd::task::Task;
 
@@ -11,15 +12,21 @@ use crate::{
     fs::{
         device::{Device, DeviceId, DeviceType},
         devpts::DevPts,
-        file_table::FdFlags,
+        file_table::FdCreationFlags,
         fs_resolver::FsPath,
         inode_handle::FileIo,
         utils::{AccessMode, Inode, InodeMode, IoctlCmd},
     },
     prelude::*,
     process::{
+        credentials::capabilities::CapSet,
         posix_thread::{AsPosixThread, AsThreadLocal},
-        signal::{PollHandle, Pollable, Pollee},
+        signal::{
+            constants::{SIGINT, SIGQUIT, SIGTSTP, SIGTTIN, SIGTTOU},
+            sig_num::SigNum,
+            signals::kernel::KernelSignal,
+            PollHandle, Pollable, Pollee,
+        },
         JobControl, Terminal,
     },
     util::ring_buffer::RingBuffer,
@@ -45,8 +52,11 @@ impl PtyMaster {
         Arc::new_cyclic(move |master| {
             let (job_control, ldisc) = new_job_control_and_ldisc();
             let slave = Arc::new_cyclic(move |weak_self| PtySlave {
+                index,
                 ldisc,
                 job_control,
+                is_locked: AtomicBool::new(true),
+                num_open_files: SpinLock::new(None),
                 master: master.clone(),
                 weak_self: weak_self.clone(),
             });
@@ -73,9 +83,34 @@ impl PtyMaster {
         &self.slave
     }
 
-    pub(super) fn slave_push_char(&self, ch: u8) {
+    /// Pushes the bytes to the input of the slave, echoing them back to the master if needed.
+    fn push_slave_input(&self, bytes: &[u8]) {
+        let mut input = self.input.lock();
+        for character in bytes {
+            self.slave.ldisc.push_char(*character, |content| {
+                for byte in content.as_bytes() {
+                    input.push_overwrite(*byte);
+                }
+            });
+        }
+
+        self.pollee.notify(IoEvents::IN);
+        self.notify_packet_status();
+    }
+
+    /// Notifies the readers of the master if there are status changes to report in the packet
+    /// mode.
+    fn notify_packet_status(&self) {
+        if self.slave.ldisc.has_packet_status() {
+            self.pollee.notify(IoEvents::IN | IoEvents::PRI);
+        }
+    }
+
+    pub(super) fn slave_push(&self, bytes: &[u8]) {
         let mut input = self.input.disable_irq().lock();
-        input.push_overwrite(ch);
+        for byte in bytes {
+            input.push_overwrite(*byte);
+        }
         self.pollee.notify(IoEvents::IN);
     }
 
@@ -84,13 +119,10 @@ impl PtyMaster {
         mask: IoEvents,
         mut poller: Option<&mut PollHandle>,
     ) -> IoEvents {
-        let mut poll_status = IoEvents::empty();
-
-        let poll_in_mask = mask & IoEvents::IN;
-        if !poll_in_mask.is_empty() {
-            let poll_in_status = self.slave.ldisc.poll(poll_in_mask, poller.as_deref_mut());
-            poll_status |= poll_in_status;
-        }
+        // The line discipline is always polled, even if `IN` is not requested, because it reports
+        // the hang-up of the terminal.
+        let poll_in_mask = mask - IoEvents::OUT;
+        let mut poll_status = self.slave.ldisc.poll(poll_in_mask, poller.as_deref_mut());
 
         let poll_out_mask = mask & IoEvents::OUT;
         if !poll_out_mask.is_empty() {
@@ -104,42 +136,66 @@ impl PtyMaster {
     }
 
     fn try_read(&self, writer: &mut VmWriter) -> Result<usize> {
+        // In the packet mode, the status changes are reported in a packet of their own, and the
+        // data are prefixed with a zero byte (i.e., `TIOCPKT_DATA`).
+        let packet_status = self.slave.ldisc.take_packet_status();
+        if !packet_status.is_empty() {
+            writer.write_val(&packet_status.bits())?;
+            self.pollee.invalidate();
+            return Ok(1);
+        }
+        let is_packet_mode = self.slave.ldisc.is_packet_mode();
+
         let mut input = self.input.disable_irq().lock();
 
         if input.is_empty() {
+            if self.slave.is_closed() {
+                return_errno_with_message!(Errno::EIO, "the slave is closed");
+            }
             return_errno_with_message!(Errno::EAGAIN, "the buffer is empty");
         }
 
-        let read_len = input.read_fallible(writer)?;
+        let mut read_len = 0;
+        if is_packet_mode {
+            writer.write_val(&0u8)?;
+            read_len += 1;
+        }
+        read_len += input.read_fallible(writer)?;
         self.pollee.invalidate();
 
         Ok(read_len)
     }
 
     fn check_io_events(&self) -> IoEvents {
+        let has_packet_status = self.slave.ldisc.has_packet_status();
         let input = self.input.disable_irq().lock();
 
-        if !input.is_empty() {
+        let events = if !input.is_empty() {
             IoEvents::IN | IoEvents::OUT
+        } else if self.slave.is_closed() {
+            IoEvents::IN | IoEvents::OUT | IoEvents::HUP
         } else {
             IoEvents::OUT
+        };
+
+        if has_packet_status {
+            events | IoEvents::IN | IoEvents::PRI
+        } else {
+            events
         }
     }
 }
 
 impl Pollable for PtyMaster {
     fn poll(&self, mask: IoEvents, mut poller: Option<&mut PollHandle>) -> IoEvents {
-        let mut poll_status = IoEvents::empty();
-
-        let poll_in_mask = mask & IoEvents::IN;
-        if !poll_in_mask.is_empty() {
-            let poll_in_status = self
-                .pollee
-                .poll_with(poll_in_mask, poller.as_deref_mut(), || {
-                    self.check_io_events()
-                });
-            poll_status |= poll_in_status;
-        }
+        // The pollee is always polled, even if neither `IN` nor `PRI` is requested, because it
+        // reports the exceptional conditions, i.e., the closing of the slave.
+        let poll_in_mask = mask - IoEvents::OUT;
+        let mut poll_status = self
+            .pollee
+            .poll_with(poll_in_mask, poller.as_deref_mut(), || {
+                self.check_io_events()
+            });
 
         let poll_out_mask = mask & IoEvents::OUT;
         if !poll_out_mask.is_empty() {
@@ -163,18 +219,8 @@ impl FileIo for PtyMaster {
 
     fn write(&self, reader: &mut VmReader) -> Result<usize> {
         let buf = reader.collect()?;
-        let write_len = buf.len();
-        let mut input = self.input.lock();
-        for character in buf {
-            self.slave.ldisc.push_char(character, |content| {
-                for byte in content.as_bytes() {
-                    input.push_overwrite(*byte);
-                }
-            });
-        }
-
-        self.pollee.notify(IoEvents::IN);
-        Ok(write_len)
+        self.push_slave_input(&buf);
+        Ok(buf.len())
     }
 
     fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
@@ -185,14 +231,20 @@ impl FileIo for PtyMaster {
             | IoctlCmd::TIOCGWINSZ
             | IoctlCmd::TIOCSWINSZ => return self.slave.ioctl(cmd, arg),
             IoctlCmd::TIOCSPTLCK => {
-                // TODO: lock/unlock pty
+                let is_locked = current_userspace!().read_val::<i32>(arg)? != 0;
+                self.slave.is_locked.store(is_locked, Ordering::Relaxed);
+            }
+            IoctlCmd::TIOCGPTLCK => {
+                let is_locked = self.slave.is_locked.load(Ordering::Relaxed) as i32;
+                current_userspace!().write_val(arg, &is_locked)?;
             }
             IoctlCmd::TIOCGPTPEER => {
                 let current_task = Task::current().unwrap();
                 let posix_thread = current_task.as_posix_thread().unwrap();
                 let thread_local = current_task.as_thread_local().unwrap();
 
-                // TODO: deal with open options
+                // TODO: deal with other open options
+                let creation_flags = FdCreationFlags::from_bits_truncate(arg as u32);
                 let slave = {
                     let slave_name = {
                         let devpts_path = super::DEV_PTS.get().unwrap().abs_path();
@@ -202,19 +254,20 @@ impl FileIo for PtyMaster {
                     let fs_path = FsPath::try_from(slave_name.as_str())?;
 
                     let inode_handle = {
-                        let fs = posix_thread.fs().resolver().read();
+                        let fs_ref = posix_thread.fs().read();
+                        let fs = fs_ref.resolver().read();
                         let flags = AccessMode::O_RDWR as u32;
                         let mode = (InodeMode::S_IRUSR | InodeMode::S_IWUSR).bits();
                         fs.open(&fs_path, flags, mode)?
                     };
                     Arc::new(inode_handle)
                 };
+                let fd_flags = creation_flags.apply_to(slave.as_ref())?;
 
                 let fd = {
                     let file_table = thread_local.borrow_file_table();
                     let mut file_table_locked = file_table.unwrap().write();
-                    // TODO: deal with the O_CLOEXEC flag
-                    file_table_locked.insert(slave, FdFlags::empty())
+                    file_table_locked.insert(slave, fd_flags)?
                 };
                 return Ok(fd);
             }
@@ -222,6 +275,43 @@ impl FileIo for PtyMaster {
                 let len = self.input.lock().len() as i32;
                 current_userspace!().write_val(arg, &len)?;
             }
+            IoctlCmd::TIOCOUTQ => {
+                // The bytes written to the master are pushed to the slave immediately, so nothing
+                // is queued for output.
+                current_userspace!().write_val(arg, &0i32)?;
+            }
+            IoctlCmd::TIOCSTI => {
+                // The master is never a controlling terminal, so the privilege is always required.
+                let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+                if !credentials.has_capability(CapSet::SYS_ADMIN) {
+                    return_errno_with_message!(
+                        Errno::EPERM,
+                        "the current thread does not have the CAP_SYS_ADMIN capability"
+                    );
+                }
+
+                // The injected byte is read from the master as if it were output by the slave.
+                let byte = current_userspace!().read_val::<u8>(arg)?;
+                self.slave_push(&[byte]);
+            }
+            IoctlCmd::TIOCPKT => {
+                let is_enabled = current_userspace!().read_val::<i32>(arg)? != 0;
+                self.slave.ldisc.set_packet_mode(is_enabled);
+            }
+            IoctlCmd::TIOCGPKT => {
+                let is_enabled = self.slave.ldisc.is_packet_mode() as i32;
+                current_userspace!().write_val(arg, &is_enabled)?;
+            }
+            IoctlCmd::TIOCSIG => {
+                // Like Linux, only the signals that can be generated by the terminal are allowed.
+                let sig_num = SigNum::try_from(arg as u8)?;
+                if sig_num != SIGINT && sig_num != SIGQUIT && sig_num != SIGTSTP {
+                    return_errno_with_message!(Errno::EINVAL, "the signal cannot be sent");
+                }
+                if let Some(foreground) = self.slave.job_control.foreground() {
+                    foreground.broadcast_signal(KernelSignal::new(sig_num));
+                }
+            }
             _ => (self.slave.clone() as Arc<dyn Terminal>).job_ioctl(cmd, arg, true)?,
         }
 
@@ -231,6 +321,8 @@ impl FileIo for PtyMaster {
 
 impl Drop for PtyMaster {
     fn drop(&mut self) {
+        self.slave.ldisc.hang_up();
+
         let fs = self.ptmx.fs();
         let devpts = fs.downcast_ref::<DevPts>().unwrap();
 
@@ -240,19 +332,34 @@ impl Drop for PtyMaster {
 }
 
 pub struct PtySlave {
+    index: u32,
     ldisc: Arc<LineDiscipline>,
     job_control: Arc<JobControl>,
+    /// Whether the slave can be opened, which is set by the master with `TIOCSPTLCK`
+    is_locked: AtomicBool,
+    /// The number of the opened files, or `None` if the slave has never been opened
+    num_open_files: SpinLock<Option<usize>>,
     master: Weak<PtyMaster>,
     weak_self: Weak<Self>,
 }
 
 impl PtySlave {
     pub fn index(&self) -> u32 {
-        self.master().index()
+        self.index
+    }
+
+    fn master(&self) -> Result<Arc<PtyMaster>> {
+        self.master
+            .upgrade()
+            .ok_or_else(|| Error::with_message(Errno::EIO, "the master is closed"))
     }
 
-    fn master(&self) -> Arc<PtyMaster> {
-        self.master.upgrade().unwrap()
+    /// Returns whether all the opened files of the slave are closed.
+    ///
+    /// The master sees the end of the slave (i.e., `EIO` for reads) only after the slave has been
+    /// opened, so the master can be set up before the slave is opened.
+    fn is_closed(&self) -> bool {
+        *self.num_open_files.lock() == Some(0)
     }
 }
 
@@ -264,43 +371,64 @@ impl Device for PtySlave {
     fn id(&self) -> crate::fs::device::DeviceId {
         DeviceId::new(88, self.index())
     }
+
+    fn open(&self) -> Result<Option<Arc<dyn FileIo>>> {
+        if self.is_locked.load(Ordering::Relaxed) {
+            return_errno_with_message!(Errno::EIO, "the slave is locked");
+        }
+        let master = self.master()?;
+
+        *self.num_open_files.lock().get_or_insert(0) += 1;
+        // The HUP event of the master may be cleared.
+        master.pollee.invalidate();
+
+        let slave = self.weak_self.upgrade().unwrap();
+        Ok(Some(Arc::new(PtySlaveFile(slave))))
+    }
 }
 
 impl Terminal for PtySlave {
     fn job_control(&self) -> &JobControl {
         &self.job_control
     }
+
+    fn push_input(&self, byte: u8) {
+        // If the master is closed, there is no one to read the input, so it is discarded.
+        if let Ok(master) = self.master() {
+            master.push_slave_input(&[byte]);
+        }
+    }
 }
 
 impl Pollable for PtySlave {
     fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
-        self.master().slave_poll(mask, poller)
+        match self.master() {
+            Ok(master) => master.slave_poll(mask, poller),
+            Err(_) => {
+                let events = IoEvents::IN | IoEvents::OUT | IoEvents::ERR | IoEvents::HUP;
+                events & (mask | IoEvents::ALWAYS_POLL)
+            }
+        }
     }
 }
 
 impl FileIo for PtySlave {
     fn read(&self, writer: &mut VmWriter) -> Result<usize> {
         let mut buf = vec![0u8; writer.avail()];
-        self.job_control.wait_until_in_foreground()?;
+        self.job_control.check_background_access(SIGTTIN)?;
         let read_len = self.ldisc.read(&mut buf)?;
         writer.write_fallible(&mut buf.as_slice().into())?;
         Ok(read_len)
     }
 
     fn write(&self, reader: &mut VmReader) -> Result<usize> {
-        let buf = reader.collect()?;
-        let write_len = buf.len();
-        let master = self.master();
-        for ch in buf {
-            // do we need to add '\r' here?
-            if ch == b'\n' {
-                master.slave_push_char(b'\r');
-                master.slave_push_char(b'\n');
-            } else {
-                master.slave_push_char(ch);
-            }
+        if self.ldisc.termios().contains_tostop() {
+            self.job_control.check_background_access(SIGTTOU)?;
         }
-        Ok(write_len)
+
+        let master = self.master()?;
+        let buf = reader.collect()?;
+        self.ldisc.write(&buf, |bytes| master.slave_push(bytes))
     }
 
     fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
@@ -310,8 +438,36 @@ impl FileIo for PtySlave {
                 current_userspace!().write_val(arg, &termios)?;
             }
             IoctlCmd::TCSETS => {
+                self.job_control.check_background_access(SIGTTOU)?;
+                let termios = current_userspace!().read_val(arg)?;
+                self.ldisc.set_termios(termios);
+            }
+            IoctlCmd::TCSETSW => {
+                self.job_control.check_background_access(SIGTTOU)?;
+                let termios = current_userspace!().read_val(arg)?;
+                self.ldisc.wait_until_sent()?;
+                self.ldisc.set_termios(termios);
+            }
+            IoctlCmd::TCSETSF => {
+                self.job_control.check_background_access(SIGTTOU)?;
                 let termios = current_userspace!().read_val(arg)?;
+                self.ldisc.wait_until_sent()?;
                 self.ldisc.set_termios(termios);
+                self.ldisc.drain_input();
+            }
+            IoctlCmd::TCSBRK => {
+                self.job_control.check_background_access(SIGTTOU)?;
+                self.ldisc.wait_until_sent()?;
+            }
+            IoctlCmd::TCXONC => {
+                self.job_control.check_background_access(SIGTTOU)?;
+                let master = self.master()?;
+                self.ldisc
+                    .control_flow(arg, |bytes| master.slave_push(bytes))?;
+            }
+            IoctlCmd::TCFLSH => {
+                self.job_control.check_background_access(SIGTTOU)?;
+                self.ldisc.flush(arg)?;
             }
             IoctlCmd::TIOCGPTN => {
                 let idx = self.index();
@@ -329,10 +485,60 @@ impl FileIo for PtySlave {
                 let buffer_len = self.ldisc.buffer_len() as i32;
                 current_userspace!().write_val(arg, &buffer_len)?;
             }
+            IoctlCmd::TIOCOUTQ => {
+                let output_len = self.ldisc.pending_output_len() as i32;
+                current_userspace!().write_val(arg, &output_len)?;
+            }
             _ => (self.weak_self.upgrade().unwrap() as Arc<dyn Terminal>)
                 .job_ioctl(cmd, arg, false)?,
         }
 
+        // The ioctl may change the status reported in the packet mode.
+        if let Ok(master) = self.master() {
+            master.notify_packet_status();
+        }
+
         Ok(0)
     }
 }
+
+/// An opened file of the pseudoterminal slave.
+///
+/// The master learns that the slave is closed when all the opened files are dropped.
+struct PtySlaveFile(Arc<PtySlave>);
+
+impl Pollable for PtySlaveFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.0.poll(mask, poller)
+    }
+}
+
+impl FileIo for PtySlaveFile {
+    fn read(&self, writer: &mut VmWriter) -> Result<usize> {
+        self.0.read(writer)
+    }
+
+    fn write(&self, reader: &mut VmReader) -> Result<usize> {
+        self.0.write(reader)
+    }
+
+    fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
+        self.0.ioctl(cmd, arg)
+    }
+}
+
+impl Drop for PtySlaveFile {
+    fn drop(&mut self) {
+        let mut num_open_files = self.0.num_open_files.lock();
+        let num = num_open_files.as_mut().unwrap();
+        *num -= 1;
+        if *num > 0 {
+            return;
+        }
+        drop(num_open_files);
+
+        if let Ok(master) = self.0.master() {
+            master.pollee.notify(IoEvents::IN | IoEvents::HUP);
+        }
+    }
+}
@@ -10,15 +10,14 @@ use crate::{
     },
     prelude::*,
     process::signal::{PollHandle, Pollable},
-    util::random::getrandom,
+    util::random::getrandom_fallible,
 };
 
 pub struct Random;
 
 impl Random {
-    pub fn getrandom(buf: &mut [u8]) -> Result<usize> {
-        getrandom(buf)?;
-        Ok(buf.len())
+    pub fn getrandom(writer: &mut VmWriter) -> Result<usize> {
+        getrandom_fallible(writer)
     }
 }
 
@@ -46,10 +45,7 @@ impl Pollable for Random {
 
 impl FileIo for Random {
     fn read(&self, writer: &mut VmWriter) -> Result<usize> {
-        let mut buf = vec![0; writer.avail()];
-        let size = Self::getrandom(buf.as_mut_slice());
-        writer.write_fallible(&mut buf.as_slice().into())?;
-        size
+        Self::getrandom(writer)
     }
 
     fn write(&self, reader: &mut VmReader) -> Result<usize> {
@@ -0,0 +1,292 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The SCSI generic devices, i.e., `/dev/sg<N>`, which are the logical units of virtio-SCSI
+//! devices.
+//!
+//! The SCSI commands are passed through to the logical units with the `SG_IO` ioctl, which is
+//! used by tools like sg3_utils and smartctl to probe the disks. Like Linux, the request is
+//! validated before the command is sent, and the SCSI status, the failures of the host adapter,
+//! and the sense data are reported back in the `sg_io_hdr`.
+//!
+//! Only the synchronous interface (i.e., `SG_IO`) of version 3 is supported. The asynchronous
+//! interface (i.e., `read` and `write`), the scatter-gather lists, and the memory-mapped I/O are
+//! not supported. The timeout of the request is ignored.
+
+use aster_time::read_monotonic_time;
+use aster_virtio::device::scsi::{
+    device::ScsiDevice, ScsiData, ScsiError, ScsiLun, ScsiResponseCode, SCSI_STATUS_CHECK_CONDITION,
+};
+
+use super::*;
+use crate::{
+    current_userspace,
+    events::IoEvents,
+    fs::{inode_handle::FileIo, utils::IoctlCmd},
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        posix_thread::AsPosixThread,
+        signal::{PollHandle, Pollable},
+    },
+};
+
+/// The version of the SCSI generic driver, i.e., 3.5.36, which is the same as Linux.
+const SG_VERSION_NUM: i32 = 30536;
+
+pub(super) fn init() -> Result<()> {
+    let mut minor = 0;
+    for (_, device) in aster_virtio::device::scsi::all_devices() {
+        for &lun in device.luns() {
+            let sg = ScsiGeneric {
+                device: device.clone(),
+                lun,
+                minor,
+            };
+            add_node(Arc::new(sg), &format!("sg{}", minor))?;
+            minor += 1;
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Clone)]
+pub struct ScsiGeneric {
+    device: Arc<ScsiDevice>,
+    lun: ScsiLun,
+    minor: u32,
+}
+
+impl Device for ScsiGeneric {
+    fn type_(&self) -> DeviceType {
+        DeviceType::CharDevice
+    }
+
+    fn id(&self) -> DeviceId {
+        // Same value with Linux
+        DeviceId::new(21, self.minor)
+    }
+
+    fn open(&self) -> Result<Option<Arc<dyn FileIo>>> {
+        Ok(Some(Arc::new(self.clone())))
+    }
+}
+
+impl ScsiGeneric {
+    fn sg_io(&self, arg: usize) -> Result<()> {
+        let user_space = current_userspace!();
+        let mut hdr: SgIoHdr = user_space.read_val(arg)?;
+
+        if hdr.interface_id != 'S' as i32 {
+            return_errno_with_message!(Errno::ENOSYS, "only the version 3 interface is supported");
+        }
+        if hdr.flags & SG_FLAG_MMAP_IO != 0 {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "the memory-mapped I/O is not supported");
+        }
+        if hdr.iovec_count != 0 {
+            return_errno_with_message!(
+                Errno::EOPNOTSUPP,
+                "the scatter-gather lists are not supported"
+            );
+        }
+        // Like Linux, the shortest CDB (i.e., a 6-byte command) is required.
+        if hdr.cmdp == 0 || hdr.cmd_len < 6 {
+            return_errno_with_message!(Errno::EMSGSIZE, "the CDB is too short");
+        }
+
+        let cdb = user_space
+            .reader(hdr.cmdp as Vaddr, hdr.cmd_len as usize)?
+            .collect()?;
+        check_command_allowed(cdb[0])?;
+
+        let dxferp = hdr.dxferp as Vaddr;
+        let dxfer_len = hdr.dxfer_len as usize;
+        let mut buf = match hdr.dxfer_direction {
+            SG_DXFER_NONE => Vec::new(),
+            SG_DXFER_TO_DEV | SG_DXFER_TO_FROM_DEV => {
+                user_space.reader(dxferp, dxfer_len)?.collect()?
+            }
+            SG_DXFER_FROM_DEV => vec![0u8; dxfer_len],
+            _ => return_errno_with_message!(Errno::EINVAL, "the transfer direction is invalid"),
+        };
+        let data = match hdr.dxfer_direction {
+            SG_DXFER_NONE => ScsiData::None,
+            SG_DXFER_TO_DEV => ScsiData::ToDevice(&buf),
+            // Like Linux, the data is copied from the user space before the command is sent,
+            // but the data is only transferred from the device.
+            _ => ScsiData::FromDevice(&mut buf),
+        };
+
+        let start = read_monotonic_time();
+        let resp = self.device.execute(self.lun, &cdb, data)?;
+        let duration = read_monotonic_time() - start;
+
+        let transferred_len = dxfer_len.saturating_sub(resp.residual as usize);
+        if matches!(
+            hdr.dxfer_direction,
+            SG_DXFER_FROM_DEV | SG_DXFER_TO_FROM_DEV
+        ) && transferred_len > 0
+        {
+            user_space.write_bytes(dxferp, &mut VmReader::from(&buf[..transferred_len]))?;
+        }
+
+        let sb_len = (hdr.mx_sb_len as usize).min(resp.sense.len());
+        if sb_len > 0 && hdr.sbp != 0 {
+            user_space.write_bytes(hdr.sbp as Vaddr, &mut VmReader::from(&resp.sense[..sb_len]))?;
+            hdr.sb_len_wr = sb_len as u8;
+        } else {
+            hdr.sb_len_wr = 0;
+        }
+
+        hdr.status = resp.status;
+        hdr.masked_status = (resp.status >> 1) & 0x7f;
+        hdr.msg_status = 0;
+        hdr.host_status = host_status(resp.code);
+        hdr.driver_status = if hdr.sb_len_wr > 0 || resp.status == SCSI_STATUS_CHECK_CONDITION {
+            DRIVER_SENSE
+        } else {
+            0
+        };
+        hdr.resid = resp.residual as i32;
+        hdr.duration = duration.as_millis() as u32;
+        hdr.info = if hdr.status != 0 || hdr.host_status != 0 || hdr.driver_status != 0 {
+            SG_INFO_CHECK
+        } else {
+            0
+        };
+
+        user_space.write_val(arg, &hdr)?;
+        Ok(())
+    }
+}
+
+impl Pollable for ScsiGeneric {
+    fn poll(&self, _mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        IoEvents::empty()
+    }
+}
+
+impl FileIo for ScsiGeneric {
+    fn read(&self, _writer: &mut VmWriter) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "the asynchronous interface is not supported");
+    }
+
+    fn write(&self, _reader: &mut VmReader) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "the asynchronous interface is not supported");
+    }
+
+    fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
+        match cmd {
+            IoctlCmd::SGIO => self.sg_io(arg)?,
+            IoctlCmd::SGGETVERSIONNUM => current_userspace!().write_val(arg, &SG_VERSION_NUM)?,
+            IoctlCmd::SGEMULATEDHOST => current_userspace!().write_val(arg, &0i32)?,
+            _ => return_errno_with_message!(Errno::ENOTTY, "unsupported ioctl"),
+        }
+        Ok(0)
+    }
+}
+
+/// Checks whether the current thread is allowed to send the command.
+///
+/// Like Linux, the commands that may modify the medium or the device require the
+/// `CAP_SYS_RAWIO` capability. The other commands are always allowed.
+fn check_command_allowed(opcode: u8) -> Result<()> {
+    // TEST UNIT READY, REQUEST SENSE, READ(6), INQUIRY, MODE SENSE(6), RECEIVE DIAGNOSTIC
+    // RESULTS, READ CAPACITY(10), READ(10), VERIFY(10), READ BUFFER, READ TOC/PMA/ATIP, LOG
+    // SENSE, MODE SENSE(10), READ(16), VERIFY(16), SERVICE ACTION IN(16) (e.g., READ
+    // CAPACITY(16)), REPORT LUNS, READ(12), and VERIFY(12).
+    const READ_ONLY_OPCODES: [u8; 19] = [
+        0x00, 0x03, 0x08, 0x12, 0x1a, 0x1c, 0x25, 0x28, 0x2f, 0x3c, 0x43, 0x4d, 0x5a, 0x88, 0x8f,
+        0x9e, 0xa0, 0xa8, 0xaf,
+    ];
+    if READ_ONLY_OPCODES.contains(&opcode) {
+        return Ok(());
+    }
+
+    let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+    if !credentials.has_capability(CapSet::SYS_RAWIO) {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "the current thread does not have the CAP_SYS_RAWIO capability"
+        );
+    }
+
+    Ok(())
+}
+
+/// Converts the response code to the host status, in the same way as Linux.
+fn host_status(code: ScsiResponseCode) -> u16 {
+    const DID_OK: u16 = 0x00;
+    const DID_BUS_BUSY: u16 = 0x02;
+    const DID_BAD_TARGET: u16 = 0x04;
+    const DID_ABORT: u16 = 0x05;
+    const DID_ERROR: u16 = 0x07;
+    const DID_RESET: u16 = 0x08;
+    const DID_TRANSPORT_DISRUPTED: u16 = 0x0e;
+    const DID_TARGET_FAILURE: u16 = 0x10;
+    const DID_NEXUS_FAILURE: u16 = 0x11;
+
+    match code {
+        ScsiResponseCode::Ok => DID_OK,
+        ScsiResponseCode::Overrun | ScsiResponseCode::Failure => DID_ERROR,
+        ScsiResponseCode::Aborted => DID_ABORT,
+        ScsiResponseCode::BadTarget => DID_BAD_TARGET,
+        ScsiResponseCode::Reset => DID_RESET,
+        ScsiResponseCode::Busy => DID_BUS_BUSY,
+        ScsiResponseCode::TransportFailure => DID_TRANSPORT_DISRUPTED,
+        ScsiResponseCode::TargetFailure => DID_TARGET_FAILURE,
+        ScsiResponseCode::NexusFailure => DID_NEXUS_FAILURE,
+    }
+}
+
+impl From<ScsiError> for Error {
+    fn from(err: ScsiError) -> Self {
+        match err {
+            ScsiError::InvalidCdb => Error::with_message(Errno::EMSGSIZE, "the CDB is too long"),
+            ScsiError::TransferTooLarge => {
+                Error::with_message(Errno::ENOMEM, "the data transfer is too large")
+            }
+        }
+    }
+}
+
+const SG_DXFER_NONE: i32 = -1;
+const SG_DXFER_TO_DEV: i32 = -2;
+const SG_DXFER_FROM_DEV: i32 = -3;
+const SG_DXFER_TO_FROM_DEV: i32 = -4;
+
+const SG_FLAG_MMAP_IO: u32 = 4;
+
+const SG_INFO_CHECK: u32 = 1;
+
+const DRIVER_SENSE: u16 = 0x08;
+
+/// The request of `SG_IO`, i.e., `struct sg_io_hdr` in Linux.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct SgIoHdr {
+    interface_id: i32,
+    dxfer_direction: i32,
+    cmd_len: u8,
+    mx_sb_len: u8,
+    iovec_count: u16,
+    dxfer_len: u32,
+    dxferp: u64,
+    cmdp: u64,
+    sbp: u64,
+    timeout: u32,
+    flags: u32,
+    pack_id: i32,
+    _pad0: u32,
+    usr_ptr: u64,
+    status: u8,
+    masked_status: u8,
+    msg_status: u8,
+    sb_len_wr: u8,
+    host_status: u16,
+    driver_status: u16,
+    resid: i32,
+    duration: u32,
+    info: u32,
+    _pad1: u32,
+}
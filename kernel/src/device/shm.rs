@@ -4,12 +4,15 @@ use crate::{
     fs::{
         fs_resolver::{FsPath, FsResolver},
         ramfs::RamFS,
-        utils::{InodeMode, InodeType},
+        utils::{FileSystem, InodeMode, InodeType},
     },
     prelude::*,
 };
 
 /// Initializes "/dev/shm" for POSIX shared memory usage.
+///
+/// `shm_open` in libc creates files under "/dev/shm", so a tmpfs (i.e., a RamFS) is mounted
+/// there. Like Linux, the root directory of the tmpfs is world-writable and sticky.
 pub fn init() -> Result<()> {
     let dev_dentry = {
         let fs = FsResolver::new();
@@ -17,9 +20,11 @@ pub fn init() -> Result<()> {
     };
 
     // Create the "shm" directory under "/dev" and mount a ramfs on it.
-    let shm_dentry =
-        dev_dentry.new_fs_child("shm", InodeType::Dir, InodeMode::from_bits_truncate(0o1777))?;
-    shm_dentry.mount(RamFS::new())?;
+    let mode = InodeMode::from_bits_truncate(0o1777);
+    let shm_dentry = dev_dentry.new_fs_child("shm", InodeType::Dir, mode)?;
+    let shm_fs = RamFS::new();
+    shm_fs.root_inode().set_mode(mode)?;
+    shm_dentry.mount(shm_fs)?;
     log::debug!("Mount RamFS at \"/dev/shm\"");
     Ok(())
 }
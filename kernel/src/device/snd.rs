@@ -0,0 +1,657 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The ALSA PCM playback device backed by a virtio-sound device, i.e., `/dev/snd/pcmC0D0p`.
+//!
+//! Only a minimal subset of the ALSA PCM interface is supported, which is enough to play
+//! interleaved PCM frames with raw ioctls:
+//! - `SNDRV_PCM_IOCTL_HW_PARAMS` chooses the smallest supported value of each parameter in the
+//!   requested range, and reports the chosen values back. Refining the parameters without setting
+//!   them (i.e., `SNDRV_PCM_IOCTL_HW_REFINE`) is not supported.
+//! - The frames are written with `write(2)` or `SNDRV_PCM_IOCTL_WRITEI_FRAMES`. The playback
+//!   starts once the buffer is full, or explicitly with `SNDRV_PCM_IOCTL_START`.
+//! - `SNDRV_PCM_IOCTL_DRAIN` waits until the written frames are played, while
+//!   `SNDRV_PCM_IOCTL_DROP` drops them immediately.
+//!
+//! Capture, memory-mapped access, and software parameters are not supported.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aster_virtio::device::sound::{
+    device::SoundDevice, PcmDirection, PcmFormat, PcmInfo, PcmParams, PcmRate, SoundError,
+    MAX_BUFFER_BYTES, MAX_PERIODS,
+};
+use spin::Once;
+
+use super::*;
+use crate::{
+    current_userspace,
+    events::IoEvents,
+    fs::{inode_handle::FileIo, utils::IoctlCmd},
+    prelude::*,
+    process::signal::{PollHandle, Pollable, Pollee},
+};
+
+/// The version of the ALSA PCM interface, i.e., 2.0.15.
+const SNDRV_PCM_VERSION: i32 = (2 << 16) | 15;
+
+/// The pollee that is notified when some periods have been played.
+static PLAYBACK_POLLEE: Once<Pollee> = Once::new();
+
+pub(super) fn init() -> Result<()> {
+    PLAYBACK_POLLEE.call_once(Pollee::new);
+
+    // Only the first playback stream is exposed now.
+    let Some((device, stream_id)) = aster_virtio::device::sound::all_devices()
+        .into_iter()
+        .find_map(|(_, device)| {
+            let stream_id = device
+                .streams()
+                .iter()
+                .position(|info| info.direction == PcmDirection::Output)?;
+            Some((device, stream_id as u32))
+        })
+    else {
+        return Ok(());
+    };
+
+    device.register_callback(&notify_playback);
+
+    let playback = PcmPlayback {
+        device,
+        stream_id,
+        is_opened: Arc::new(AtomicBool::new(false)),
+    };
+    add_node(Arc::new(playback), "snd/pcmC0D0p")?;
+
+    Ok(())
+}
+
+fn notify_playback() {
+    PLAYBACK_POLLEE.get().unwrap().notify(IoEvents::OUT);
+}
+
+pub struct PcmPlayback {
+    device: Arc<SoundDevice>,
+    stream_id: u32,
+    is_opened: Arc<AtomicBool>,
+}
+
+impl Device for PcmPlayback {
+    fn type_(&self) -> DeviceType {
+        DeviceType::CharDevice
+    }
+
+    fn id(&self) -> DeviceId {
+        // Same value with Linux
+        DeviceId::new(116, 16)
+    }
+
+    fn open(&self) -> Result<Option<Arc<dyn FileIo>>> {
+        // Like Linux, a PCM substream can only be opened once at a time.
+        if self.is_opened.swap(true, Ordering::Acquire) {
+            return_errno_with_message!(Errno::EBUSY, "the PCM substream is already opened");
+        }
+
+        Ok(Some(Arc::new(PcmFile {
+            device: self.device.clone(),
+            stream_id: self.stream_id,
+            info: self.device.streams()[self.stream_id as usize],
+            is_opened: self.is_opened.clone(),
+            state: Mutex::new(PcmState::Open),
+        })))
+    }
+}
+
+impl Pollable for PcmPlayback {
+    fn poll(&self, _mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        IoEvents::empty()
+    }
+}
+
+impl FileIo for PcmPlayback {
+    fn read(&self, _writer: &mut VmWriter) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "cannot read the PCM device");
+    }
+
+    fn write(&self, _reader: &mut VmReader) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "cannot write the PCM device");
+    }
+}
+
+/// The state of an opened PCM substream, which follows the state machine of ALSA.
+#[derive(Debug, Clone, Copy)]
+enum PcmState {
+    /// The hardware parameters are not set.
+    Open,
+    /// The hardware parameters are set.
+    Setup(PcmParams),
+    /// The substream is ready to play.
+    Prepared(PcmParams),
+    /// The substream is playing.
+    Running(PcmParams),
+}
+
+/// An opened `/dev/snd/pcmC0D0p`.
+struct PcmFile {
+    device: Arc<SoundDevice>,
+    stream_id: u32,
+    info: PcmInfo,
+    is_opened: Arc<AtomicBool>,
+    state: Mutex<PcmState>,
+}
+
+impl PcmFile {
+    fn set_hw_params(&self, arg: usize) -> Result<()> {
+        let mut hw_params: SndPcmHwParams = current_userspace!().read_val(arg)?;
+
+        let mut state = self.state.lock();
+        match *state {
+            PcmState::Open | PcmState::Setup(_) => (),
+            PcmState::Prepared(_) => self.device.release(self.stream_id)?,
+            PcmState::Running(_) => {
+                return_errno_with_message!(Errno::EBADFD, "the PCM substream is running")
+            }
+        }
+        *state = PcmState::Open;
+
+        let params = hw_params.choose(&self.info)?;
+        *state = PcmState::Setup(params);
+        drop(state);
+
+        current_userspace!().write_val(arg, &hw_params)?;
+        Ok(())
+    }
+
+    fn free_hw_params(&self) -> Result<()> {
+        let mut state = self.state.lock();
+        match *state {
+            PcmState::Open | PcmState::Setup(_) => (),
+            PcmState::Prepared(_) => self.device.release(self.stream_id)?,
+            PcmState::Running(_) => {
+                return_errno_with_message!(Errno::EBADFD, "the PCM substream is running")
+            }
+        }
+        *state = PcmState::Open;
+
+        Ok(())
+    }
+
+    fn prepare(&self) -> Result<()> {
+        let mut state = self.state.lock();
+        let params = match *state {
+            PcmState::Open => {
+                return_errno_with_message!(Errno::EBADFD, "the hardware parameters are not set")
+            }
+            PcmState::Setup(params) => params,
+            PcmState::Prepared(params) | PcmState::Running(params) => {
+                self.stop_and_release(&mut state)?;
+                params
+            }
+        };
+
+        self.device.set_params(self.stream_id, &params)?;
+        self.device.prepare(self.stream_id)?;
+        *state = PcmState::Prepared(params);
+
+        Ok(())
+    }
+
+    fn start(&self) -> Result<()> {
+        let mut state = self.state.lock();
+        let PcmState::Prepared(params) = *state else {
+            return_errno_with_message!(Errno::EBADFD, "the PCM substream is not prepared");
+        };
+
+        self.device.start(self.stream_id)?;
+        *state = PcmState::Running(params);
+
+        Ok(())
+    }
+
+    fn drop_frames(&self) -> Result<()> {
+        let mut state = self.state.lock();
+        match *state {
+            PcmState::Open => {
+                return_errno_with_message!(Errno::EBADFD, "the hardware parameters are not set")
+            }
+            PcmState::Setup(_) => Ok(()),
+            PcmState::Prepared(_) | PcmState::Running(_) => self.stop_and_release(&mut state),
+        }
+    }
+
+    fn drain(&self) -> Result<()> {
+        {
+            let mut state = self.state.lock();
+            match *state {
+                PcmState::Open => {
+                    return_errno_with_message!(Errno::EBADFD, "the hardware parameters are not set")
+                }
+                PcmState::Setup(_) => return Ok(()),
+                PcmState::Prepared(params) => {
+                    self.device.flush()?;
+                    // Like Linux, the frames that have been written are played before stopping.
+                    if !self.device.is_drained() {
+                        self.device.start(self.stream_id)?;
+                        *state = PcmState::Running(params);
+                    }
+                }
+                PcmState::Running(_) => self.device.flush()?,
+            }
+        }
+
+        self.wait_events(IoEvents::OUT, None, || {
+            if self.device.is_drained() {
+                Ok(())
+            } else {
+                Err(Error::with_message(
+                    Errno::EAGAIN,
+                    "the frames are being played",
+                ))
+            }
+        })?;
+
+        let mut state = self.state.lock();
+        match *state {
+            PcmState::Prepared(_) | PcmState::Running(_) => self.stop_and_release(&mut state),
+            // The substream has been stopped by someone else.
+            PcmState::Open | PcmState::Setup(_) => Ok(()),
+        }
+    }
+
+    /// Stops and releases the substream, moving it to the [`PcmState::Setup`] state.
+    fn stop_and_release(&self, state: &mut PcmState) -> Result<()> {
+        let (PcmState::Prepared(params) | PcmState::Running(params)) = *state else {
+            return Ok(());
+        };
+
+        if matches!(state, PcmState::Running(_)) {
+            self.device.stop(self.stream_id)?;
+        }
+        self.device.release(self.stream_id)?;
+        *state = PcmState::Setup(params);
+
+        Ok(())
+    }
+
+    fn write_frames(&self, reader: &mut VmReader) -> Result<usize> {
+        let frame_bytes = match *self.state.lock() {
+            PcmState::Prepared(params) | PcmState::Running(params) => params.frame_bytes(),
+            PcmState::Open | PcmState::Setup(_) => {
+                return_errno_with_message!(Errno::EBADFD, "the PCM substream is not prepared")
+            }
+        };
+
+        let len = reader.remain() / frame_bytes * frame_bytes;
+        if len == 0 {
+            return Ok(0);
+        }
+        let frames = reader.limit(len).collect()?;
+
+        let mut written_bytes = 0;
+        while written_bytes < len {
+            match self.wait_events(IoEvents::OUT, None, || {
+                self.try_write(&frames[written_bytes..])
+            }) {
+                Ok(nbytes) => written_bytes += nbytes,
+                // Report the frames that have been written, if any.
+                Err(_) if written_bytes > 0 => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(written_bytes)
+    }
+
+    fn try_write(&self, frames: &[u8]) -> Result<usize> {
+        let mut state = self.state.lock();
+        let (PcmState::Prepared(params) | PcmState::Running(params)) = *state else {
+            return_errno_with_message!(Errno::EBADFD, "the PCM substream is not prepared");
+        };
+
+        let nbytes = self.device.write(frames)?;
+
+        // Like the default start threshold of ALSA, start playing once the buffer is full.
+        if matches!(*state, PcmState::Prepared(_)) && !self.device.can_write() {
+            self.device.start(self.stream_id)?;
+            *state = PcmState::Running(params);
+        }
+
+        if nbytes == 0 {
+            return_errno_with_message!(Errno::EAGAIN, "the buffer is full");
+        }
+        Ok(nbytes)
+    }
+
+    fn write_frames_ioctl(&self, arg: usize) -> Result<()> {
+        let user_space = current_userspace!();
+        let mut xferi: SndXferi = user_space.read_val(arg)?;
+
+        let frame_bytes = match *self.state.lock() {
+            PcmState::Prepared(params) | PcmState::Running(params) => params.frame_bytes(),
+            PcmState::Open | PcmState::Setup(_) => {
+                return_errno_with_message!(Errno::EBADFD, "the PCM substream is not prepared")
+            }
+        };
+        let len = (xferi.frames as usize)
+            .checked_mul(frame_bytes)
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "too many frames"))?;
+
+        let mut reader = user_space.reader(xferi.buf as Vaddr, len)?;
+        let written_bytes = self.write_frames(&mut reader)?;
+
+        xferi.result = (written_bytes / frame_bytes) as i64;
+        user_space.write_val(arg, &xferi)?;
+        Ok(())
+    }
+
+    fn info(&self) -> SndPcmInfo {
+        let mut info = SndPcmInfo::new_zeroed();
+        info.subdevices_count = 1;
+        copy_c_str(&mut info.id, "virtio-snd");
+        copy_c_str(&mut info.name, "VirtIO PCM 0");
+        copy_c_str(&mut info.subname, "subdevice #0");
+        info
+    }
+}
+
+impl Pollable for PcmFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        if let Some(poller) = poller {
+            PLAYBACK_POLLEE.get().unwrap().register_poller(poller, mask);
+        }
+
+        let mut events = IoEvents::empty();
+        if self.device.can_write() {
+            events |= IoEvents::OUT;
+        }
+        events & mask
+    }
+}
+
+impl FileIo for PcmFile {
+    fn read(&self, _writer: &mut VmWriter) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "capture is not supported");
+    }
+
+    fn write(&self, reader: &mut VmReader) -> Result<usize> {
+        self.write_frames(reader)
+    }
+
+    fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
+        match cmd {
+            IoctlCmd::PCMPVERSION => current_userspace!().write_val(arg, &SNDRV_PCM_VERSION)?,
+            IoctlCmd::PCMINFO => current_userspace!().write_val(arg, &self.info())?,
+            IoctlCmd::PCMHWPARAMS => self.set_hw_params(arg)?,
+            IoctlCmd::PCMHWFREE => self.free_hw_params()?,
+            IoctlCmd::PCMPREPARE => self.prepare()?,
+            IoctlCmd::PCMSTART => self.start()?,
+            IoctlCmd::PCMDROP => self.drop_frames()?,
+            IoctlCmd::PCMDRAIN => self.drain()?,
+            IoctlCmd::PCMWRITEIFRAMES => self.write_frames_ioctl(arg)?,
+            _ => return_errno_with_message!(Errno::ENOTTY, "unsupported ioctl"),
+        }
+        Ok(0)
+    }
+}
+
+impl Drop for PcmFile {
+    fn drop(&mut self) {
+        let mut state = self.state.lock();
+        let _ = self.stop_and_release(&mut state);
+        drop(state);
+
+        self.is_opened.store(false, Ordering::Release);
+    }
+}
+
+impl From<SoundError> for Error {
+    fn from(err: SoundError) -> Self {
+        match err {
+            SoundError::InvalidStream => {
+                Error::with_message(Errno::ENODEV, "the PCM stream does not exist")
+            }
+            SoundError::InvalidParams => {
+                Error::with_message(Errno::EINVAL, "the PCM parameters are not supported")
+            }
+            SoundError::InvalidState => {
+                Error::with_message(Errno::EBADFD, "the PCM stream is not set up")
+            }
+            SoundError::Busy => Error::with_message(Errno::EBUSY, "the PCM stream is playing"),
+            SoundError::BadMessage => {
+                Error::with_message(Errno::EINVAL, "the sound device rejected the request")
+            }
+            SoundError::NotSupported => {
+                Error::with_message(Errno::EOPNOTSUPP, "the sound device does not support it")
+            }
+            SoundError::IoError => Error::with_message(Errno::EIO, "the sound device failed"),
+        }
+    }
+}
+
+fn copy_c_str(dst: &mut [u8], src: &str) {
+    // Keep the last byte as the null terminator.
+    let len = src.len().min(dst.len() - 1);
+    dst[..len].copy_from_slice(&src.as_bytes()[..len]);
+}
+
+/// The information of a PCM substream, i.e., `struct snd_pcm_info` in Linux.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct SndPcmInfo {
+    device: u32,
+    subdevice: u32,
+    stream: i32,
+    card: i32,
+    id: [u8; 64],
+    name: [u8; 80],
+    subname: [u8; 32],
+    dev_class: i32,
+    dev_subclass: i32,
+    subdevices_count: u32,
+    subdevices_avail: u32,
+    sync: [u8; 16],
+    reserved: [u8; 64],
+}
+
+/// The argument of `SNDRV_PCM_IOCTL_WRITEI_FRAMES`, i.e., `struct snd_xferi` in Linux.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct SndXferi {
+    result: i64,
+    buf: u64,
+    frames: u64,
+}
+
+/// A set of values, i.e., `struct snd_mask` in Linux.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct SndMask {
+    bits: [u32; 8],
+}
+
+impl SndMask {
+    fn contains(&self, bit: usize) -> bool {
+        self.bits[bit / 32] & (1 << (bit % 32)) != 0
+    }
+
+    fn set_only(&mut self, bit: usize) {
+        self.bits = [0; 8];
+        self.bits[bit / 32] = 1 << (bit % 32);
+    }
+}
+
+/// A range of values, i.e., `struct snd_interval` in Linux.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct SndInterval {
+    min: u32,
+    max: u32,
+    /// The bit fields of `openmin`, `openmax`, `integer`, and `empty`.
+    flags: u32,
+}
+
+impl SndInterval {
+    const OPENMIN: u32 = 1 << 0;
+    const OPENMAX: u32 = 1 << 1;
+    const INTEGER: u32 = 1 << 2;
+
+    /// Returns the smallest value in both the interval and `lo..=hi`.
+    fn choose(&self, lo: u32, hi: u32) -> Option<u32> {
+        let min = if self.flags & Self::OPENMIN != 0 {
+            self.min.checked_add(1)?
+        } else {
+            self.min
+        };
+        let max = if self.flags & Self::OPENMAX != 0 {
+            self.max.checked_sub(1)?
+        } else {
+            self.max
+        };
+
+        let value = min.max(lo);
+        (value <= max.min(hi)).then_some(value)
+    }
+
+    fn set_only(&mut self, value: u32) {
+        self.min = value;
+        self.max = value;
+        self.flags = Self::INTEGER;
+    }
+}
+
+/// The hardware parameters, i.e., `struct snd_pcm_hw_params` in Linux.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct SndPcmHwParams {
+    flags: u32,
+    masks: [SndMask; 3],
+    mres: [SndMask; 5],
+    intervals: [SndInterval; 12],
+    ires: [SndInterval; 9],
+    rmask: u32,
+    cmask: u32,
+    info: u32,
+    msbits: u32,
+    rate_num: u32,
+    rate_den: u32,
+    fifo_size: u64,
+    reserved: [u8; 64],
+}
+
+// The indexes of the masks.
+const SNDRV_PCM_HW_PARAM_ACCESS: usize = 0;
+const SNDRV_PCM_HW_PARAM_FORMAT: usize = 1;
+const SNDRV_PCM_HW_PARAM_SUBFORMAT: usize = 2;
+
+// The indexes of the intervals.
+const SNDRV_PCM_HW_PARAM_SAMPLE_BITS: usize = 0;
+const SNDRV_PCM_HW_PARAM_FRAME_BITS: usize = 1;
+const SNDRV_PCM_HW_PARAM_CHANNELS: usize = 2;
+const SNDRV_PCM_HW_PARAM_RATE: usize = 3;
+const SNDRV_PCM_HW_PARAM_PERIOD_TIME: usize = 4;
+const SNDRV_PCM_HW_PARAM_PERIOD_SIZE: usize = 5;
+const SNDRV_PCM_HW_PARAM_PERIOD_BYTES: usize = 6;
+const SNDRV_PCM_HW_PARAM_PERIODS: usize = 7;
+const SNDRV_PCM_HW_PARAM_BUFFER_TIME: usize = 8;
+const SNDRV_PCM_HW_PARAM_BUFFER_SIZE: usize = 9;
+const SNDRV_PCM_HW_PARAM_BUFFER_BYTES: usize = 10;
+const SNDRV_PCM_HW_PARAM_TICK_TIME: usize = 11;
+
+const SNDRV_PCM_ACCESS_RW_INTERLEAVED: usize = 3;
+const SNDRV_PCM_SUBFORMAT_STD: usize = 0;
+const SNDRV_PCM_INFO_INTERLEAVED: u32 = 0x100;
+
+/// The ALSA formats that can be mapped to the virtio-sound formats, in the order of preference.
+const FORMATS: [(usize, PcmFormat); 7] = [
+    (2, PcmFormat::S16),
+    (0, PcmFormat::S8),
+    (1, PcmFormat::U8),
+    (4, PcmFormat::U16),
+    (10, PcmFormat::S32),
+    (12, PcmFormat::U32),
+    (14, PcmFormat::Float),
+];
+
+impl SndPcmHwParams {
+    /// Chooses the parameters supported by the stream, and updates the hardware parameters
+    /// accordingly.
+    fn choose(&mut self, info: &PcmInfo) -> Result<PcmParams> {
+        let invalid = || Error::with_message(Errno::EINVAL, "the hardware parameters are invalid");
+
+        if !self.masks[SNDRV_PCM_HW_PARAM_ACCESS].contains(SNDRV_PCM_ACCESS_RW_INTERLEAVED) {
+            return_errno_with_message!(Errno::EINVAL, "only the interleaved access is supported");
+        }
+        let (alsa_format, format) = FORMATS
+            .into_iter()
+            .find(|(alsa_format, format)| {
+                self.masks[SNDRV_PCM_HW_PARAM_FORMAT].contains(*alsa_format)
+                    && info.supports_format(*format)
+            })
+            .ok_or_else(invalid)?;
+
+        let interval = |index: usize| &self.intervals[index];
+        let channels = interval(SNDRV_PCM_HW_PARAM_CHANNELS)
+            .choose(info.channels_min as u32, info.channels_max as u32)
+            .ok_or_else(invalid)?;
+        let rate = PcmRate::ALL
+            .into_iter()
+            .find(|rate| {
+                info.supports_rate(*rate)
+                    && interval(SNDRV_PCM_HW_PARAM_RATE)
+                        .choose(rate.hz(), rate.hz())
+                        .is_some()
+            })
+            .ok_or_else(invalid)?;
+
+        let frame_bytes = (format.sample_bytes() * channels as usize) as u32;
+        let max_frames = MAX_BUFFER_BYTES as u32 / frame_bytes;
+        let period_size = interval(SNDRV_PCM_HW_PARAM_PERIOD_SIZE)
+            .choose(1, max_frames)
+            .ok_or_else(invalid)?;
+        let periods = {
+            let buffer_size = interval(SNDRV_PCM_HW_PARAM_BUFFER_SIZE);
+            let lo = buffer_size.min.div_ceil(period_size).max(1);
+            let hi = (max_frames / period_size).min(MAX_PERIODS as u32);
+            interval(SNDRV_PCM_HW_PARAM_PERIODS)
+                .choose(lo, hi)
+                .ok_or_else(invalid)?
+        };
+        let buffer_size = period_size * periods;
+        let sample_bits = format.sample_bytes() as u32 * 8;
+        let time_us = |frames: u32| (frames as u64 * 1_000_000 / rate.hz() as u64) as u32;
+
+        self.masks[SNDRV_PCM_HW_PARAM_ACCESS].set_only(SNDRV_PCM_ACCESS_RW_INTERLEAVED);
+        self.masks[SNDRV_PCM_HW_PARAM_FORMAT].set_only(alsa_format);
+        self.masks[SNDRV_PCM_HW_PARAM_SUBFORMAT].set_only(SNDRV_PCM_SUBFORMAT_STD);
+        for (index, value) in [
+            (SNDRV_PCM_HW_PARAM_SAMPLE_BITS, sample_bits),
+            (SNDRV_PCM_HW_PARAM_FRAME_BITS, frame_bytes * 8),
+            (SNDRV_PCM_HW_PARAM_CHANNELS, channels),
+            (SNDRV_PCM_HW_PARAM_RATE, rate.hz()),
+            (SNDRV_PCM_HW_PARAM_PERIOD_TIME, time_us(period_size)),
+            (SNDRV_PCM_HW_PARAM_PERIOD_SIZE, period_size),
+            (SNDRV_PCM_HW_PARAM_PERIOD_BYTES, period_size * frame_bytes),
+            (SNDRV_PCM_HW_PARAM_PERIODS, periods),
+            (SNDRV_PCM_HW_PARAM_BUFFER_TIME, time_us(buffer_size)),
+            (SNDRV_PCM_HW_PARAM_BUFFER_SIZE, buffer_size),
+            (SNDRV_PCM_HW_PARAM_BUFFER_BYTES, buffer_size * frame_bytes),
+            (SNDRV_PCM_HW_PARAM_TICK_TIME, 0),
+        ] {
+            self.intervals[index].set_only(value);
+        }
+        self.cmask = self.rmask;
+        self.info = SNDRV_PCM_INFO_INTERLEAVED;
+        self.msbits = sample_bits;
+        self.rate_num = rate.hz();
+        self.rate_den = 1;
+        self.fifo_size = 0;
+
+        Ok(PcmParams {
+            format,
+            rate,
+            channels: channels as u8,
+            period_bytes: period_size * frame_bytes,
+            buffer_bytes: buffer_size * frame_bytes,
+        })
+    }
+}
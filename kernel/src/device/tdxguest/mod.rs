@@ -1,7 +1,15 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use ostd::mm::{DmaCoherent, FrameAllocOptions, HasPaddr, VmIo};
-use tdx_guest::tdcall::{get_report, TdCallError};
+use core::time::Duration;
+
+use ostd::{
+    mm::{DmaCoherent, FrameAllocOptions, HasPaddr, UFrame, VmIo, PAGE_SIZE},
+    sync::Waiter,
+};
+use tdx_guest::{
+    tdcall::{extend_rtmr, get_report, TdCallError},
+    tdvmcall::{get_quote, TdVmcallError},
+};
 
 use super::*;
 use crate::{
@@ -9,11 +17,44 @@ use crate::{
     events::IoEvents,
     fs::{inode_handle::FileIo, utils::IoctlCmd},
     process::signal::{PollHandle, Pollable},
+    time::clocks::MonotonicClock,
 };
 
 const TDX_REPORTDATA_LEN: usize = 64;
+/// The size of a measurement, which is the size of a SHA-384 digest.
+pub(super) const TDX_MEASUREMENT_LEN: usize = 48;
 const TDX_REPORT_LEN: usize = 1024;
 
+/// The bit in a guest physical address that marks the page as shared with the VMM.
+const SHARED_BIT: u8 = 51;
+const SHARED_MASK: u64 = 1u64 << SHARED_BIT;
+
+/// The maximum size of the buffer used by the `GetQuote` hypercall.
+///
+/// This follows the limit used by Linux's TDX guest driver.
+const GET_QUOTE_MAX_SIZE: usize = 8 * PAGE_SIZE;
+/// The version of the `GetQuote` buffer header defined in the TDX GHCI specification.
+const GET_QUOTE_HEADER_VERSION: u64 = 1;
+/// The status value indicating that the VMM has not finished the quote generation.
+const GET_QUOTE_IN_FLIGHT: u64 = 0xffff_ffff_ffff_ffff;
+/// The status value indicating that the quote has been generated successfully.
+const GET_QUOTE_SUCCESS: u64 = 0;
+/// The interval of polling the `GetQuote` buffer for the completion.
+const GET_QUOTE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// The maximum time of waiting for the VMM to generate a quote.
+const GET_QUOTE_TIMEOUT: Duration = Duration::from_secs(30);
+/// The maximum number of the parked `GetQuote` buffers.
+///
+/// New requests are rejected if the VMM does not complete this many abandoned requests.
+const MAX_PARKED_QUOTE_BUFFERS: usize = 4;
+
+/// The `GetQuote` buffers of the abandoned requests that are still in flight.
+///
+/// If the caller stops waiting for a quote (e.g., due to a signal or a timeout), the VMM may still
+/// write to the buffer at any time, so the buffer cannot be freed and reused until the VMM
+/// completes the request. The parked buffers are checked and freed on the next requests.
+static PARKED_QUOTE_BUFFERS: Mutex<Vec<DmaCoherent>> = Mutex::new(Vec::new());
+
 #[derive(Debug, Clone, Copy, Pod)]
 #[repr(C)]
 pub struct TdxReportRequest {
@@ -21,6 +62,28 @@ pub struct TdxReportRequest {
     tdx_report: [u8; TDX_REPORT_LEN],
 }
 
+/// The argument of the `TDX_CMD_GET_QUOTE` ioctl.
+///
+/// `buf` points to a user buffer of `len` bytes which starts with a [`TdxQuoteHeader`].
+/// The TDX report is placed after the header as the input, and the generated quote is
+/// written back to the same place.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub struct TdxQuoteRequest {
+    buf: u64,
+    len: u64,
+}
+
+/// The header of the buffer shared with the VMM by the `GetQuote` hypercall.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct TdxQuoteHeader {
+    version: u64,
+    status: u64,
+    in_len: u32,
+    out_len: u32,
+}
+
 pub struct TdxGuest;
 
 impl Device for TdxGuest {
@@ -57,6 +120,26 @@ impl From<TdCallError> for Error {
     }
 }
 
+impl From<TdVmcallError> for Error {
+    fn from(err: TdVmcallError) -> Self {
+        match err {
+            TdVmcallError::TdxRetry => {
+                Error::with_message(Errno::EAGAIN, "TdVmcallError::TdxRetry")
+            }
+            TdVmcallError::TdxOperandInvalid => {
+                Error::with_message(Errno::EINVAL, "TdVmcallError::TdxOperandInvalid")
+            }
+            TdVmcallError::TdxGpaInuse => {
+                Error::with_message(Errno::EBUSY, "TdVmcallError::TdxGpaInuse")
+            }
+            TdVmcallError::TdxAlignError => {
+                Error::with_message(Errno::EINVAL, "TdVmcallError::TdxAlignError")
+            }
+            _ => Error::with_message(Errno::EIO, "TdVmcallError::Other"),
+        }
+    }
+}
+
 impl Pollable for TdxGuest {
     fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
         let events = IoEvents::IN | IoEvents::OUT;
@@ -76,14 +159,25 @@ impl FileIo for TdxGuest {
     fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
         match cmd {
             IoctlCmd::TDXGETREPORT => handle_get_report(arg),
+            IoctlCmd::TDXGETQUOTE => handle_get_quote(arg),
             _ => return_errno_with_message!(Errno::EPERM, "Unsupported ioctl"),
         }
     }
 }
 
+/// Extends the RTMR (runtime measurement register) with the measurement.
+pub(super) fn extend_runtime_measurement(
+    rtmr_index: u64,
+    measurement: &[u8; TDX_MEASUREMENT_LEN],
+) -> Result<()> {
+    // The measurement is passed in a 64-byte aligned buffer in the private memory.
+    let frame: UFrame = FrameAllocOptions::new().alloc_frame()?.into();
+    frame.write_bytes(0, measurement)?;
+    extend_rtmr(frame.start_paddr() as u64, rtmr_index)?;
+    Ok(())
+}
+
 fn handle_get_report(arg: usize) -> Result<i32> {
-    const SHARED_BIT: u8 = 51;
-    const SHARED_MASK: u64 = 1u64 << SHARED_BIT;
     let current_task = ostd::task::Task::current().unwrap();
     let user_space = CurrentUserSpace::new(&current_task);
     let user_request: TdxReportRequest = user_space.read_val(arg)?;
@@ -115,3 +209,101 @@ fn handle_get_report(arg: usize) -> Result<i32> {
     user_space.write_bytes(tdx_report_vaddr, &mut VmReader::from(report_slice))?;
     Ok(0)
 }
+
+fn handle_get_quote(arg: usize) -> Result<i32> {
+    let current_task = ostd::task::Task::current().unwrap();
+    let user_space = CurrentUserSpace::new(&current_task);
+    let user_request: TdxQuoteRequest = user_space.read_val(arg)?;
+
+    {
+        let mut parked_buffers = PARKED_QUOTE_BUFFERS.lock();
+        parked_buffers.retain(is_quote_in_flight);
+        if parked_buffers.len() >= MAX_PARKED_QUOTE_BUFFERS {
+            return_errno_with_message!(Errno::EBUSY, "too many quote requests are in flight");
+        }
+    }
+
+    let buf_len = user_request.len as usize;
+    if buf_len <= size_of::<TdxQuoteHeader>() || buf_len > GET_QUOTE_MAX_SIZE {
+        return_errno_with_message!(Errno::EINVAL, "invalid quote buffer length");
+    }
+
+    let mut header: TdxQuoteHeader = user_space.read_val(user_request.buf as Vaddr)?;
+    if header.version != GET_QUOTE_HEADER_VERSION {
+        return_errno_with_message!(Errno::EINVAL, "unsupported quote buffer version");
+    }
+    if header.in_len as usize > buf_len - size_of::<TdxQuoteHeader>() {
+        return_errno_with_message!(Errno::EINVAL, "the input exceeds the quote buffer");
+    }
+
+    let mut buf = vec![0u8; buf_len];
+    user_space.read_bytes(
+        user_request.buf as Vaddr,
+        &mut VmWriter::from(buf.as_mut_slice()),
+    )?;
+
+    // The VMM requires the buffer to be shared and the status to be zero on submission.
+    header.status = 0;
+    header.out_len = 0;
+    buf[..size_of::<TdxQuoteHeader>()].copy_from_slice(header.as_bytes());
+
+    let nr_pages = buf_len.div_ceil(PAGE_SIZE);
+    let segment = FrameAllocOptions::new().alloc_segment(nr_pages)?;
+    let dma_coherent = DmaCoherent::map(segment.into(), false).map_err(|_| {
+        Error::with_message(Errno::ENOMEM, "failed to map the quote buffer for DMA")
+    })?;
+    dma_coherent.write_bytes(0, buf.as_slice()).unwrap();
+
+    get_quote(
+        (dma_coherent.paddr() as u64) | SHARED_MASK,
+        (nr_pages * PAGE_SIZE) as u64,
+    )?;
+
+    if let Err(err) = wait_for_quote(&dma_coherent) {
+        PARKED_QUOTE_BUFFERS.lock().push(dma_coherent);
+        return Err(err);
+    }
+
+    dma_coherent.read_bytes(0, buf.as_mut_slice()).unwrap();
+    user_space.write_bytes(
+        user_request.buf as Vaddr,
+        &mut VmReader::from(buf.as_slice()),
+    )?;
+
+    let header: TdxQuoteHeader = dma_coherent.read_val(0).unwrap();
+    if header.status != GET_QUOTE_SUCCESS {
+        println!(
+            "[kernel]: get TDX quote error: status = {:#x}",
+            header.status
+        );
+        return_errno_with_message!(Errno::EIO, "the VMM failed to generate the quote");
+    }
+
+    Ok(0)
+}
+
+/// Waits until the VMM completes the `GetQuote` request.
+///
+/// The quote is generated asynchronously by the VMM (and the quoting enclave behind it), so we
+/// have to poll the status in the header of the buffer.
+fn wait_for_quote(dma_coherent: &DmaCoherent) -> Result<()> {
+    let deadline = MonotonicClock::get().read_time() + GET_QUOTE_TIMEOUT;
+    let waiter = Waiter::new_pair().0;
+    while is_quote_in_flight(dma_coherent) {
+        if MonotonicClock::get().read_time() >= deadline {
+            return_errno_with_message!(Errno::ETIMEDOUT, "the VMM did not generate the quote");
+        }
+        match waiter.pause_timeout(&(&GET_QUOTE_POLL_INTERVAL).into()) {
+            Err(err) if err.error() == Errno::ETIME => continue,
+            Err(err) => return Err(err),
+            Ok(()) => continue,
+        }
+    }
+
+    Ok(())
+}
+
+fn is_quote_in_flight(dma_coherent: &DmaCoherent) -> bool {
+    let header: TdxQuoteHeader = dma_coherent.read_val(0).unwrap();
+    header.status == GET_QUOTE_IN_FLIGHT
+}
@@ -1,13 +1,14 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use alloc::format;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 use ostd::{
-    sync::LocalIrqDisabled,
+    sync::{LocalIrqDisabled, WaitQueue},
     trap::{disable_local, in_interrupt_context},
 };
 
-use super::termio::{KernelTermios, WinSize, CC_C_CHAR};
+use super::termio::{KernelTermios, WinSize, CC_C_CHAR, C_OFLAGS};
 use crate::{
     events::IoEvents,
     prelude::*,
@@ -32,6 +33,8 @@ pub type LdiscSignalSender = Arc<dyn Fn(KernelSignal) + Send + Sync + 'static>;
 // 2. `current_line`
 // 3. `read_buffer`
 // 4. `work_item_para`
+// 5. `output`
+// 6. `packet_status`
 pub struct LineDiscipline {
     /// Current line
     current_line: SpinLock<CurrentLine, LocalIrqDisabled>,
@@ -43,6 +46,14 @@ pub struct LineDiscipline {
     winsize: SpinLock<WinSize, LocalIrqDisabled>,
     /// Pollee
     pollee: Pollee,
+    /// Whether the terminal is hung up, e.g., after the pseudoterminal master is closed
+    is_hung_up: AtomicBool,
+    /// The state of the output
+    output: SpinLock<OutputState, LocalIrqDisabled>,
+    /// Used to wait for the stopped output to be restarted or the pending output to be sent
+    output_wait_queue: WaitQueue,
+    /// The status changes to report in the packet mode, or `None` if the packet mode is disabled
+    packet_status: SpinLock<Option<PacketStatus>, LocalIrqDisabled>,
     /// Used to send signal for foreground processes, when some char comes.
     send_signal: LdiscSignalSender,
     /// Work item
@@ -90,6 +101,43 @@ impl CurrentLine {
     }
 }
 
+bitflags! {
+    /// The status changes of a terminal, which are reported to the pseudoterminal master in the
+    /// packet mode (see `TIOCPKT`).
+    pub struct PacketStatus: u8 {
+        /// The input is discarded.
+        const FLUSHREAD  = 0x01;
+        /// The output is discarded.
+        const FLUSHWRITE = 0x02;
+        /// The output is stopped.
+        const STOP       = 0x04;
+        /// The output is restarted.
+        const START      = 0x08;
+        /// The output can no longer be stopped and restarted with `^S` and `^Q`.
+        const NOSTOP     = 0x10;
+        /// The output can be stopped and restarted with `^S` and `^Q`.
+        const DOSTOP     = 0x20;
+        /// The termios is changed while `EXTPROC` is set.
+        const IOCTL      = 0x40;
+    }
+}
+
+/// The state of the output.
+///
+/// The output is sent as soon as it is written, unless the output is stopped. The writers of the
+/// stopped output wait for the output to be restarted, and their bytes are pending meanwhile.
+#[derive(Default)]
+struct OutputState {
+    /// Whether the output is stopped with `VSTOP` or `TCOOFF`
+    is_stopped: bool,
+    /// The number of the pending bytes
+    pending_len: usize,
+    /// The number of times that the pending bytes are discarded
+    num_discards: usize,
+    /// The column of the cursor, which is used to expand tabs
+    column: usize,
+}
+
 impl Pollable for LineDiscipline {
     fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
         self.pollee
@@ -113,6 +161,10 @@ impl LineDiscipline {
                 termios: SpinLock::new(KernelTermios::default()),
                 winsize: SpinLock::new(WinSize::default()),
                 pollee: Pollee::new(),
+                is_hung_up: AtomicBool::new(false),
+                output: SpinLock::new(OutputState::default()),
+                output_wait_queue: WaitQueue::new(),
+                packet_status: SpinLock::new(None),
                 send_signal,
                 work_item,
                 work_item_para: Arc::new(SpinLock::new(LineDisciplineWorkPara::new())),
@@ -130,6 +182,20 @@ impl LineDiscipline {
             ch
         };
 
+        if termios.contains_ixon() {
+            if ch == *termios.get_special_char(CC_C_CHAR::VSTOP) {
+                self.stop_output();
+                return;
+            }
+            if ch == *termios.get_special_char(CC_C_CHAR::VSTART) {
+                self.start_output();
+                return;
+            }
+            if termios.contains_ixany() {
+                self.start_output();
+            }
+        }
+
         if self.may_send_signal(&termios, ch) {
             submit_work_item(self.work_item.clone(), WorkPriority::High);
             // CBREAK mode may require the character to be outputted, so just go ahead.
@@ -150,6 +216,15 @@ impl LineDiscipline {
 
         // Canonical mode
 
+        let is_editing_char = ch == *termios.get_special_char(CC_C_CHAR::VKILL)
+            || ch == *termios.get_special_char(CC_C_CHAR::VERASE);
+        if is_editing_char && termios.contains_extproc() {
+            // With EXTPROC, the editing is done by the external process, so the editing characters
+            // are passed through as they are.
+            self.current_line.lock().push_char(ch);
+            return;
+        }
+
         if ch == *termios.get_special_char(CC_C_CHAR::VKILL) {
             // Erase current line
             self.current_line.lock().drain();
@@ -204,11 +279,15 @@ impl LineDiscipline {
     fn check_io_events(&self) -> IoEvents {
         let buffer = self.read_buffer.lock();
 
-        if !buffer.is_empty() {
+        let mut events = if !buffer.is_empty() {
             IoEvents::IN
         } else {
             IoEvents::empty()
+        };
+        if self.is_hung_up() {
+            events |= IoEvents::IN | IoEvents::HUP;
         }
+        events
     }
 
     /// Sends a signal later. The signal will be handled by a work queue.
@@ -218,17 +297,24 @@ impl LineDiscipline {
         };
     }
 
-    // TODO: respect output flags
     fn output_char<F: FnMut(&str)>(&self, ch: u8, termios: &KernelTermios, mut echo_callback: F) {
         match ch {
-            b'\n' => echo_callback("\n"),
-            b'\r' => echo_callback("\r\n"),
-            ch if ch == *termios.get_special_char(CC_C_CHAR::VERASE) => {
+            b'\n' | b'\r' | b'\t' => {
+                let processed = process_output(&[ch], termios, &mut self.output.lock().column);
+                // The processed bytes of the ASCII characters are all ASCII characters.
+                echo_callback(core::str::from_utf8(&processed).unwrap());
+            }
+            ch if ch == *termios.get_special_char(CC_C_CHAR::VERASE)
+                && !termios.contains_extproc() =>
+            {
                 // write a space to overwrite current character
                 let backspace: &str = core::str::from_utf8(b"\x08 \x08").unwrap();
                 echo_callback(backspace);
             }
-            ch if is_printable_char(ch) => print!("{}", char::from(ch)),
+            ch if is_printable_char(ch) => {
+                let processed = process_output(&[ch], termios, &mut self.output.lock().column);
+                echo_callback(core::str::from_utf8(&processed).unwrap());
+            }
             ch if is_ctrl_char(ch) && termios.contains_echo_ctl() => {
                 let ctrl_char = format!("^{}", get_printable_char(ch));
                 echo_callback(&ctrl_char);
@@ -237,8 +323,17 @@ impl LineDiscipline {
         }
     }
 
+    /// Reads the bytes into `buf`.
+    ///
+    /// If the terminal is hung up, this method returns 0 (i.e., the end of the file) once the
+    /// buffered bytes are all read.
     pub fn read(&self, buf: &mut [u8]) -> Result<usize> {
-        self.wait_events(IoEvents::IN, None, || self.try_read(buf))
+        self.wait_events(IoEvents::IN, None, || {
+            if self.is_hung_up() && self.is_empty() {
+                return Ok(0);
+            }
+            self.try_read(buf)
+        })
     }
 
     /// Reads all bytes buffered to `dst`.
@@ -335,18 +430,220 @@ impl LineDiscipline {
         self.read_buffer.lock().len() == 0
     }
 
+    /// Writes `buf` to the terminal, returning the number of bytes written.
+    ///
+    /// The bytes are processed according to the output flags and then passed to `send`. If the
+    /// output is stopped, this method waits for the output to be restarted. The bytes are dropped
+    /// if they are discarded meanwhile (e.g., with `TCOFLUSH`).
+    pub fn write<F: FnOnce(&[u8])>(&self, buf: &[u8], send: F) -> Result<usize> {
+        let (processed, num_discards) = {
+            let termios = self.termios.lock();
+            let mut output = self.output.lock();
+            let processed = process_output(buf, &termios, &mut output.column);
+
+            if !output.is_stopped {
+                (processed, None)
+            } else {
+                output.pending_len += processed.len();
+                (processed, Some(output.num_discards))
+            }
+        };
+
+        if let Some(num_discards) = num_discards {
+            let is_restarted = self.output_wait_queue.pause_until(|| {
+                let output = self.output.lock();
+                if output.num_discards != num_discards {
+                    Some(false)
+                } else if !output.is_stopped {
+                    Some(true)
+                } else {
+                    None
+                }
+            });
+
+            {
+                let mut output = self.output.lock();
+                if output.num_discards == num_discards {
+                    output.pending_len -= processed.len();
+                }
+            }
+            // Wake up the waiters of `wait_until_sent`.
+            self.output_wait_queue.wake_all();
+
+            if !is_restarted? {
+                return Ok(buf.len());
+            }
+        }
+
+        send(&processed);
+        Ok(buf.len())
+    }
+
+    /// Stops the output, e.g., with `VSTOP` or `TCOOFF`.
+    pub fn stop_output(&self) {
+        let mut output = self.output.lock();
+        if output.is_stopped {
+            return;
+        }
+        output.is_stopped = true;
+        drop(output);
+
+        self.update_packet_status(|status| {
+            status.remove(PacketStatus::START);
+            status.insert(PacketStatus::STOP);
+        });
+    }
+
+    /// Restarts the output, e.g., with `VSTART` or `TCOON`.
+    pub fn start_output(&self) {
+        let mut output = self.output.lock();
+        if !output.is_stopped {
+            return;
+        }
+        output.is_stopped = false;
+        drop(output);
+        self.output_wait_queue.wake_all();
+
+        self.update_packet_status(|status| {
+            status.remove(PacketStatus::STOP);
+            status.insert(PacketStatus::START);
+        });
+    }
+
+    /// Discards the pending output.
+    pub fn discard_output(&self) {
+        let mut output = self.output.lock();
+        if output.pending_len > 0 {
+            output.pending_len = 0;
+            output.num_discards = output.num_discards.wrapping_add(1);
+        }
+        drop(output);
+        self.output_wait_queue.wake_all();
+
+        self.update_packet_status(|status| status.insert(PacketStatus::FLUSHWRITE));
+    }
+
+    /// Returns the number of the pending bytes in the output, which are waiting for the output to
+    /// be restarted.
+    pub fn pending_output_len(&self) -> usize {
+        self.output.lock().pending_len
+    }
+
+    /// Waits until the pending output is sent or discarded.
+    pub fn wait_until_sent(&self) -> Result<()> {
+        self.output_wait_queue
+            .pause_until(|| (self.output.lock().pending_len == 0).then_some(()))
+    }
+
+    /// Performs the flow control `action` of `TCXONC`.
+    ///
+    /// The `VSTOP` and `VSTART` characters for `TCIOFF` and `TCION` are passed to `send`, even if
+    /// the output is stopped.
+    pub fn control_flow<F: FnOnce(&[u8])>(&self, action: usize, send: F) -> Result<()> {
+        match action {
+            TCOOFF => self.stop_output(),
+            TCOON => self.start_output(),
+            TCIOFF => send(&[*self.termios().get_special_char(CC_C_CHAR::VSTOP)]),
+            TCION => send(&[*self.termios().get_special_char(CC_C_CHAR::VSTART)]),
+            _ => return_errno_with_message!(Errno::EINVAL, "the flow control action is invalid"),
+        }
+        Ok(())
+    }
+
+    /// Discards the input, the pending output, or both, as specified by `queue` of `TCFLSH`.
+    pub fn flush(&self, queue: usize) -> Result<()> {
+        match queue {
+            TCIFLUSH => self.drain_input(),
+            TCOFLUSH => self.discard_output(),
+            TCIOFLUSH => {
+                self.drain_input();
+                self.discard_output();
+            }
+            _ => return_errno_with_message!(Errno::EINVAL, "the queue to flush is invalid"),
+        }
+        Ok(())
+    }
+
+    /// Hangs up the terminal, so the readers see the end of the file.
+    pub fn hang_up(&self) {
+        self.is_hung_up.store(true, Ordering::Relaxed);
+        self.pollee.notify(IoEvents::IN | IoEvents::HUP);
+    }
+
+    /// Returns whether the terminal is hung up.
+    pub fn is_hung_up(&self) -> bool {
+        self.is_hung_up.load(Ordering::Relaxed)
+    }
+
     pub fn termios(&self) -> KernelTermios {
         *self.termios.lock()
     }
 
     pub fn set_termios(&self, termios: KernelTermios) {
-        *self.termios.lock() = termios;
+        let old_termios = core::mem::replace(&mut *self.termios.lock(), termios);
+
+        self.update_packet_status(|status| {
+            let can_stop = has_default_flow_control(&termios);
+            if has_default_flow_control(&old_termios) != can_stop {
+                status.remove(PacketStatus::DOSTOP | PacketStatus::NOSTOP);
+                status.insert(if can_stop {
+                    PacketStatus::DOSTOP
+                } else {
+                    PacketStatus::NOSTOP
+                });
+            }
+            if old_termios.contains_extproc() || termios.contains_extproc() {
+                status.insert(PacketStatus::IOCTL);
+            }
+        });
     }
 
     pub fn drain_input(&self) {
         self.current_line.lock().drain();
         self.read_buffer.lock().clear();
         self.pollee.invalidate();
+
+        self.update_packet_status(|status| status.insert(PacketStatus::FLUSHREAD));
+    }
+
+    /// Enables or disables the packet mode.
+    ///
+    /// The status changes are recorded only in the packet mode, and they are cleared when the
+    /// packet mode is enabled.
+    pub fn set_packet_mode(&self, is_enabled: bool) {
+        let mut packet_status = self.packet_status.lock();
+        if !is_enabled {
+            *packet_status = None;
+        } else if packet_status.is_none() {
+            *packet_status = Some(PacketStatus::empty());
+        }
+    }
+
+    /// Returns whether the packet mode is enabled.
+    pub fn is_packet_mode(&self) -> bool {
+        self.packet_status.lock().is_some()
+    }
+
+    /// Returns whether there are status changes that have not been reported.
+    pub fn has_packet_status(&self) -> bool {
+        self.packet_status
+            .lock()
+            .is_some_and(|status| !status.is_empty())
+    }
+
+    /// Takes the status changes that have not been reported.
+    pub fn take_packet_status(&self) -> PacketStatus {
+        self.packet_status
+            .lock()
+            .as_mut()
+            .map(|status| core::mem::replace(status, PacketStatus::empty()))
+            .unwrap_or_else(PacketStatus::empty)
+    }
+
+    fn update_packet_status<F: FnOnce(&mut PacketStatus)>(&self, f: F) {
+        if let Some(status) = self.packet_status.lock().as_mut() {
+            f(status);
+        }
     }
 
     pub fn buffer_len(&self) -> usize {
@@ -381,6 +678,96 @@ fn is_eof(ch: u8, termios: &KernelTermios) -> bool {
     ch == *termios.get_special_char(CC_C_CHAR::VEOF)
 }
 
+/// Returns whether the output can be stopped and restarted with `^S` and `^Q`.
+///
+/// This decides whether `NOSTOP` or `DOSTOP` is reported in the packet mode, like Linux does.
+fn has_default_flow_control(termios: &KernelTermios) -> bool {
+    termios.contains_ixon()
+        && *termios.get_special_char(CC_C_CHAR::VSTOP) == b'\x13'
+        && *termios.get_special_char(CC_C_CHAR::VSTART) == b'\x11'
+}
+
+// The actions of `TCXONC`.
+const TCOOFF: usize = 0;
+const TCOON: usize = 1;
+const TCIOFF: usize = 2;
+const TCION: usize = 3;
+
+// The queues of `TCFLSH`.
+const TCIFLUSH: usize = 0;
+const TCOFLUSH: usize = 1;
+const TCIOFLUSH: usize = 2;
+
+/// Processes the output bytes according to the output flags.
+///
+/// The `column` of the cursor is updated for the tab expansion. This is similar to the
+/// `do_output_char` function in Linux:
+/// <https://elixir.bootlin.com/linux/v6.0.9/source/drivers/tty/n_tty.c#L400>
+fn process_output(buf: &[u8], termios: &KernelTermios, column: &mut usize) -> Vec<u8> {
+    let flags = termios.output_flags();
+    if !flags.contains(C_OFLAGS::OPOST) {
+        return buf.to_vec();
+    }
+
+    let mut processed = Vec::with_capacity(buf.len());
+    for &ch in buf {
+        match ch {
+            b'\n' => {
+                if flags.contains(C_OFLAGS::ONLRET) {
+                    *column = 0;
+                }
+                if flags.contains(C_OFLAGS::ONLCR) {
+                    *column = 0;
+                    processed.push(b'\r');
+                }
+                processed.push(b'\n');
+            }
+            b'\r' => {
+                if flags.contains(C_OFLAGS::ONOCR) && *column == 0 {
+                    continue;
+                }
+                if flags.contains(C_OFLAGS::OCRNL) {
+                    if flags.contains(C_OFLAGS::ONLRET) {
+                        *column = 0;
+                    }
+                    processed.push(b'\n');
+                } else {
+                    *column = 0;
+                    processed.push(b'\r');
+                }
+            }
+            b'\t' => {
+                let num_spaces = 8 - *column % 8;
+                *column += num_spaces;
+                if flags & C_OFLAGS::TABDLY == C_OFLAGS::XTABS {
+                    processed.resize(processed.len() + num_spaces, b' ');
+                } else {
+                    processed.push(b'\t');
+                }
+            }
+            b'\x08' => {
+                *column = column.saturating_sub(1);
+                processed.push(ch);
+            }
+            _ => {
+                let ch = if flags.contains(C_OFLAGS::OLCUC) {
+                    ch.to_ascii_uppercase()
+                } else {
+                    ch
+                };
+                // The continuation bytes of UTF-8 do not take columns.
+                let is_continuation = termios.contains_iutf8() && (ch & 0xc0) == 0x80;
+                if !ch.is_ascii_control() && !is_continuation {
+                    *column += 1;
+                }
+                processed.push(ch);
+            }
+        }
+    }
+
+    processed
+}
+
 fn is_printable_char(ch: u8) -> bool {
     (0x20..0x7f).contains(&ch)
 }
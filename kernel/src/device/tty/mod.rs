@@ -14,7 +14,11 @@ use crate::{
     },
     prelude::*,
     process::{
-        signal::{signals::kernel::KernelSignal, PollHandle, Pollable},
+        signal::{
+            constants::{SIGTTIN, SIGTTOU},
+            signals::kernel::KernelSignal,
+            PollHandle, Pollable,
+        },
         JobControl, Terminal,
     },
 };
@@ -80,20 +84,19 @@ impl Pollable for Tty {
 impl FileIo for Tty {
     fn read(&self, writer: &mut VmWriter) -> Result<usize> {
         let mut buf = vec![0; writer.avail()];
-        self.job_control.wait_until_in_foreground()?;
+        self.job_control.check_background_access(SIGTTIN)?;
         let read_len = self.ldisc.read(buf.as_mut_slice())?;
         writer.write_fallible(&mut buf.as_slice().into())?;
         Ok(read_len)
     }
 
     fn write(&self, reader: &mut VmReader) -> Result<usize> {
-        let buf = reader.collect()?;
-        if let Ok(content) = alloc::str::from_utf8(&buf) {
-            print!("{content}");
-        } else {
-            println!("Not utf-8 content: {:?}", buf);
+        if self.ldisc.termios().contains_tostop() {
+            self.job_control.check_background_access(SIGTTOU)?;
         }
-        Ok(buf.len())
+
+        let buf = reader.collect()?;
+        self.ldisc.write(&buf, send_to_consoles)
     }
 
     fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
@@ -105,23 +108,39 @@ impl FileIo for Tty {
                 current_userspace!().write_val(arg, &termios)?;
             }
             IoctlCmd::TCSETS => {
+                self.job_control.check_background_access(SIGTTOU)?;
                 // Set terminal attributes
                 let termios = current_userspace!().read_val(arg)?;
                 debug!("set termios = {:?}", termios);
                 self.ldisc.set_termios(termios);
             }
             IoctlCmd::TCSETSW => {
+                self.job_control.check_background_access(SIGTTOU)?;
                 let termios = current_userspace!().read_val(arg)?;
                 debug!("set termios = {:?}", termios);
+                self.ldisc.wait_until_sent()?;
                 self.ldisc.set_termios(termios);
-                // TODO: drain output buffer
             }
             IoctlCmd::TCSETSF => {
+                self.job_control.check_background_access(SIGTTOU)?;
                 let termios = current_userspace!().read_val(arg)?;
                 debug!("set termios = {:?}", termios);
+                self.ldisc.wait_until_sent()?;
                 self.ldisc.set_termios(termios);
                 self.ldisc.drain_input();
-                // TODO: drain output buffer
+            }
+            IoctlCmd::TCSBRK => {
+                self.job_control.check_background_access(SIGTTOU)?;
+                // There is no line to send a break to, so only the output is waited for.
+                self.ldisc.wait_until_sent()?;
+            }
+            IoctlCmd::TCXONC => {
+                self.job_control.check_background_access(SIGTTOU)?;
+                self.ldisc.control_flow(arg, send_to_consoles)?;
+            }
+            IoctlCmd::TCFLSH => {
+                self.job_control.check_background_access(SIGTTOU)?;
+                self.ldisc.flush(arg)?;
             }
             IoctlCmd::TIOCGWINSZ => {
                 let winsize = self.ldisc.window_size();
@@ -131,6 +150,14 @@ impl FileIo for Tty {
                 let winsize = current_userspace!().read_val(arg)?;
                 self.ldisc.set_window_size(winsize);
             }
+            IoctlCmd::FIONREAD => {
+                let buffer_len = self.ldisc.buffer_len() as i32;
+                current_userspace!().write_val(arg, &buffer_len)?;
+            }
+            IoctlCmd::TIOCOUTQ => {
+                let output_len = self.ldisc.pending_output_len() as i32;
+                current_userspace!().write_val(arg, &output_len)?;
+            }
             _ => (self.weak_self.upgrade().unwrap() as Arc<dyn Terminal>)
                 .job_ioctl(cmd, arg, false)?,
         }
@@ -143,6 +170,10 @@ impl Terminal for Tty {
     fn job_control(&self) -> &JobControl {
         &self.job_control
     }
+
+    fn push_input(&self, byte: u8) {
+        self.push_char(byte);
+    }
 }
 
 impl Device for Tty {
@@ -156,6 +187,13 @@ impl Device for Tty {
     }
 }
 
+/// Sends the output of the console TTY to the preferred console devices.
+fn send_to_consoles(buf: &[u8]) {
+    aster_console::console_table_lock()
+        .tty_consoles()
+        .for_each(|console| console.send(buf));
+}
+
 pub fn new_job_control_and_ldisc() -> (Arc<JobControl>, Arc<LineDiscipline>) {
     let job_control = Arc::new(JobControl::new());
 
@@ -54,6 +54,8 @@ bitflags! {
         const ONLRET = 1 << 5;
         const OFILL  = 1 << 6;
         const OFDEL  = 1 << 7;
+        const TABDLY = 0x1800;			/* Tab delay mask */
+        const XTABS  = 0x1800;			/* Expand tabs to spaces */
     }
 }
 
@@ -286,6 +288,34 @@ impl KernelTermios {
     pub fn contains_iexten(&self) -> bool {
         self.c_lflags.contains(C_LFLAGS::IEXTEN)
     }
+
+    pub fn contains_tostop(&self) -> bool {
+        self.c_lflags.contains(C_LFLAGS::TOSTOP)
+    }
+
+    /// IXON means the output can be stopped and restarted with `VSTOP` and `VSTART`
+    pub fn contains_ixon(&self) -> bool {
+        self.c_iflags.contains(C_IFLAGS::IXON)
+    }
+
+    /// IXANY means any character can restart the stopped output
+    pub fn contains_ixany(&self) -> bool {
+        self.c_iflags.contains(C_IFLAGS::IXANY)
+    }
+
+    /// EXTPROC means the input editing is done by an external process (e.g., a remote login
+    /// client), so the line discipline does not handle the editing characters
+    pub fn contains_extproc(&self) -> bool {
+        self.c_lflags.contains(C_LFLAGS::EXTPROC)
+    }
+
+    pub fn contains_iutf8(&self) -> bool {
+        self.c_iflags.contains(C_IFLAGS::IUTF8)
+    }
+
+    pub fn output_flags(&self) -> C_OFLAGS {
+        self.c_oflags
+    }
 }
 
 const fn control_character(c: char) -> u8 {
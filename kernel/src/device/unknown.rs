@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::*;
+use crate::{
+    events::IoEvents,
+    fs::inode_handle::FileIo,
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+};
+
+/// A device that is not supported by the kernel.
+///
+/// The device nodes of such devices only record their device numbers. Opening them fails with
+/// `ENXIO`, as on Linux.
+pub struct UnknownDevice {
+    type_: DeviceType,
+    id: DeviceId,
+}
+
+impl UnknownDevice {
+    pub fn new(type_: DeviceType, id: DeviceId) -> Self {
+        Self { type_, id }
+    }
+}
+
+impl Device for UnknownDevice {
+    fn type_(&self) -> DeviceType {
+        self.type_
+    }
+
+    fn id(&self) -> DeviceId {
+        self.id
+    }
+
+    fn open(&self) -> Result<Option<Arc<dyn FileIo>>> {
+        return_errno_with_message!(Errno::ENXIO, "the device is not supported");
+    }
+}
+
+impl Pollable for UnknownDevice {
+    fn poll(&self, _mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        IoEvents::empty()
+    }
+}
+
+impl FileIo for UnknownDevice {
+    fn read(&self, _writer: &mut VmWriter) -> Result<usize> {
+        return_errno_with_message!(Errno::ENXIO, "the device is not supported");
+    }
+
+    fn write(&self, _reader: &mut VmReader) -> Result<usize> {
+        return_errno_with_message!(Errno::ENXIO, "the device is not supported");
+    }
+}
@@ -32,6 +32,19 @@ use super::Events;
 pub trait Observer<E: Events>: Send + Sync {
     /// Notify the observer that some interesting events happen.
     fn on_events(&self, events: &E);
+
+    /// Notify the exclusive observer that some interesting events happen.
+    ///
+    /// The method returns whether the observer has taken the events. If it returns `false`, the
+    /// events will be delivered to the next exclusive observer. If no exclusive observer takes the
+    /// events, all of them will be notified via [`Observer::on_events`]. See
+    /// [`Subject::register_exclusive_observer`] for details.
+    ///
+    /// [`Subject::register_exclusive_observer`]: super::Subject::register_exclusive_observer
+    fn on_exclusive_events(&self, events: &E) -> bool {
+        self.on_events(events);
+        true
+    }
 }
 
 impl<E: Events> Observer<E> for () {
@@ -10,8 +10,8 @@ use crate::prelude::*;
 
 /// A Subject notifies interesting events to registered observers.
 pub struct Subject<E: Events, F: EventsFilter<E> = ()> {
-    // A table that maintains all interesting observers.
-    observers: SpinLock<BTreeMap<KeyableWeak<dyn Observer<E>>, F>, LocalIrqDisabled>,
+    // A table that maintains all interesting observers and whether they are exclusive.
+    observers: SpinLock<BTreeMap<KeyableWeak<dyn Observer<E>>, (F, bool)>, LocalIrqDisabled>,
     // To reduce lock contentions, we maintain a counter for the size of the table
     num_observers: AtomicUsize,
 }
@@ -32,10 +32,26 @@ impl<E: Events, F: EventsFilter<E>> Subject<E, F> {
     /// If the given observer has already been registered, then its registered events
     /// filter will be updated.
     pub fn register_observer(&self, observer: Weak<dyn Observer<E>>, filter: F) {
+        self.do_register_observer(observer, filter, false);
+    }
+
+    /// Register an exclusive observer.
+    ///
+    /// Unlike an ordinary observer, an exclusive observer does not get notified of every event.
+    /// Each notification is delivered to all the ordinary observers but only one of the exclusive
+    /// observers, which is the first one whose [`Observer::on_exclusive_events`] returns `true`.
+    /// If none of them returns `true`, all the exclusive observers are notified.
+    /// This avoids waking up many waiters for events that can be consumed by only one of them,
+    /// e.g., incoming connections of a listening socket.
+    pub fn register_exclusive_observer(&self, observer: Weak<dyn Observer<E>>, filter: F) {
+        self.do_register_observer(observer, filter, true);
+    }
+
+    fn do_register_observer(&self, observer: Weak<dyn Observer<E>>, filter: F, is_exclusive: bool) {
         let mut observers = self.observers.lock();
         let is_new = {
             let observer: KeyableWeak<dyn Observer<E>> = observer.into();
-            observers.insert(observer, filter).is_none()
+            observers.insert(observer, (filter, is_exclusive)).is_none()
         };
         if is_new {
             // This `Acquire` pairs with the `Release` in `notify_observers`.
@@ -43,6 +59,13 @@ impl<E: Events, F: EventsFilter<E>> Subject<E, F> {
         }
     }
 
+    /// Returns whether there are any registered observers.
+    ///
+    /// The result may be stale if observers are registered or unregistered concurrently.
+    pub fn has_observers(&self) -> bool {
+        self.num_observers.load(Ordering::Relaxed) != 0
+    }
+
     /// Unregister an observer.
     ///
     /// If such an observer is found, then the registered observer will be
@@ -78,13 +101,18 @@ impl<E: Events, F: EventsFilter<E>> Subject<E, F> {
 
         // Slow path: broadcast the new events to all observers.
         let mut active_observers = Vec::new();
+        let mut exclusive_observers = Vec::new();
         let mut num_freed = 0;
         let mut observers = self.observers.lock();
-        observers.retain(|observer, filter| {
+        observers.retain(|observer, (filter, is_exclusive)| {
             if let Some(observer) = observer.upgrade() {
                 if filter.filter(events) {
                     // XXX: Mind the performance impact when there comes many active observers
-                    active_observers.push(observer.clone());
+                    if *is_exclusive {
+                        exclusive_observers.push(observer);
+                    } else {
+                        active_observers.push(observer);
+                    }
                 }
                 true
             } else {
@@ -100,6 +128,15 @@ impl<E: Events, F: EventsFilter<E>> Subject<E, F> {
         for observer in active_observers {
             observer.on_events(events);
         }
+        if exclusive_observers
+            .iter()
+            .any(|observer| observer.on_exclusive_events(events))
+        {
+            return;
+        }
+        for observer in exclusive_observers {
+            observer.on_events(events);
+        }
     }
 }
 
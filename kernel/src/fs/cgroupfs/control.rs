@@ -0,0 +1,280 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::fmt::Write;
+
+use crate::{
+    prelude::*,
+    process::{
+        cgroup::{Cgroup, Controllers},
+        process_table, Pid,
+    },
+};
+
+/// An interface file of a cgroup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ControlFile {
+    /// The controllers that can be enabled for the children (`cgroup.controllers`).
+    Controllers,
+    /// The controllers that are enabled for the children (`cgroup.subtree_control`).
+    SubtreeControl,
+    /// The PIDs of the processes in the cgroup (`cgroup.procs`).
+    Procs,
+    /// Whether the cgroup or its descendants have processes, and whether they are frozen
+    /// (`cgroup.events`).
+    Events,
+    /// Whether the cgroup is frozen (`cgroup.freeze`).
+    Freeze,
+    /// The CPU bandwidth limit (`cpu.max`).
+    CpuMax,
+    /// The CPU usage statistics (`cpu.stat`).
+    CpuStat,
+    /// The memory usage limit (`memory.max`).
+    MemoryMax,
+    /// The memory usage (`memory.current`).
+    MemoryCurrent,
+}
+
+impl ControlFile {
+    const ALL: [Self; 9] = [
+        Self::Controllers,
+        Self::SubtreeControl,
+        Self::Procs,
+        Self::Events,
+        Self::Freeze,
+        Self::CpuMax,
+        Self::CpuStat,
+        Self::MemoryMax,
+        Self::MemoryCurrent,
+    ];
+
+    /// Returns the interface files of the cgroup.
+    pub(super) fn all_of(cgroup: &Cgroup) -> impl Iterator<Item = Self> + '_ {
+        Self::ALL
+            .into_iter()
+            .filter(|file| !cgroup.is_root() || !file.is_non_root_only())
+    }
+
+    /// Returns the interface file of the cgroup with the given name.
+    pub(super) fn lookup(cgroup: &Cgroup, name: &str) -> Option<Self> {
+        Self::all_of(cgroup).find(|file| file.name() == name)
+    }
+
+    pub(super) fn name(&self) -> &'static str {
+        match self {
+            Self::Controllers => "cgroup.controllers",
+            Self::SubtreeControl => "cgroup.subtree_control",
+            Self::Procs => "cgroup.procs",
+            Self::Events => "cgroup.events",
+            Self::Freeze => "cgroup.freeze",
+            Self::CpuMax => "cpu.max",
+            Self::CpuStat => "cpu.stat",
+            Self::MemoryMax => "memory.max",
+            Self::MemoryCurrent => "memory.current",
+        }
+    }
+
+    fn is_non_root_only(&self) -> bool {
+        matches!(
+            self,
+            Self::Events | Self::Freeze | Self::CpuMax | Self::MemoryMax | Self::MemoryCurrent
+        )
+    }
+
+    pub(super) fn is_writable(&self) -> bool {
+        matches!(
+            self,
+            Self::SubtreeControl | Self::Procs | Self::Freeze | Self::CpuMax | Self::MemoryMax
+        )
+    }
+
+    /// Generates the contents of the file.
+    pub(super) fn read(&self, cgroup: &Arc<Cgroup>) -> String {
+        let mut output = String::new();
+        match self {
+            Self::Controllers => {
+                let controllers = match cgroup.parent() {
+                    Some(parent) => parent.subtree_control(),
+                    None => Controllers::all(),
+                };
+                writeln!(output, "{}", controllers.names()).unwrap();
+            }
+            Self::SubtreeControl => {
+                writeln!(output, "{}", cgroup.subtree_control().names()).unwrap();
+            }
+            Self::Procs => {
+                for process in cgroup.processes() {
+                    writeln!(output, "{}", process.pid()).unwrap();
+                }
+            }
+            Self::Events => {
+                writeln!(output, "populated {}", cgroup.is_populated() as u8).unwrap();
+                writeln!(output, "frozen {}", cgroup.is_frozen() as u8).unwrap();
+            }
+            Self::Freeze => {
+                writeln!(output, "{}", cgroup.freeze() as u8).unwrap();
+            }
+            Self::CpuMax => {
+                let (quota_us, period_us) = cgroup.cpu_bandwidth().max();
+                match quota_us {
+                    Some(quota_us) => writeln!(output, "{} {}", quota_us, period_us),
+                    None => writeln!(output, "max {}", period_us),
+                }
+                .unwrap();
+            }
+            Self::CpuStat => {
+                let stat = cgroup.cpu_bandwidth().stat();
+                writeln!(output, "usage_usec {}", stat.usage_us).unwrap();
+                writeln!(output, "nr_periods {}", stat.nr_periods).unwrap();
+                writeln!(output, "nr_throttled {}", stat.nr_throttled).unwrap();
+            }
+            Self::MemoryMax => match cgroup.memory_max() {
+                Some(max) => writeln!(output, "{}", max).unwrap(),
+                None => writeln!(output, "max").unwrap(),
+            },
+            Self::MemoryCurrent => {
+                writeln!(output, "{}", cgroup.memory_current()).unwrap();
+            }
+        }
+        output
+    }
+
+    /// Parses the contents written to the file and applies them to the cgroup.
+    pub(super) fn write(&self, cgroup: &Arc<Cgroup>, input: &str) -> Result<()> {
+        let input = input.trim();
+        match self {
+            Self::SubtreeControl => write_subtree_control(cgroup, input),
+            Self::Procs => write_procs(cgroup, input),
+            Self::Freeze => write_freeze(cgroup, input),
+            Self::CpuMax => write_cpu_max(cgroup, input),
+            Self::MemoryMax => write_memory_max(cgroup, input),
+            _ => return_errno_with_message!(Errno::EACCES, "the cgroup file is read-only"),
+        }
+    }
+}
+
+/// Parses `+<controller>` and `-<controller>` tokens separated by spaces.
+fn write_subtree_control(cgroup: &Cgroup, input: &str) -> Result<()> {
+    let available = match cgroup.parent() {
+        Some(parent) => parent.subtree_control(),
+        None => Controllers::all(),
+    };
+
+    let mut enabled = Controllers::empty();
+    let mut disabled = Controllers::empty();
+    for token in input.split_whitespace() {
+        let (is_enabled, name) = if let Some(name) = token.strip_prefix('+') {
+            (true, name)
+        } else if let Some(name) = token.strip_prefix('-') {
+            (false, name)
+        } else {
+            return_errno_with_message!(Errno::EINVAL, "the controller must be prefixed by + or -");
+        };
+
+        let Some(controller) = Controllers::from_name(name) else {
+            return_errno_with_message!(Errno::EINVAL, "the controller does not exist");
+        };
+        if !available.contains(controller) {
+            return_errno_with_message!(Errno::ENOENT, "the controller is not available");
+        }
+
+        if is_enabled {
+            enabled |= controller;
+            disabled -= controller;
+        } else {
+            disabled |= controller;
+            enabled -= controller;
+        }
+    }
+
+    cgroup.update_subtree_control(enabled, disabled);
+    Ok(())
+}
+
+/// Parses a PID, where zero means the current process.
+fn write_procs(cgroup: &Arc<Cgroup>, input: &str) -> Result<()> {
+    let Ok(pid) = input.parse::<Pid>() else {
+        return_errno_with_message!(Errno::EINVAL, "the PID is invalid");
+    };
+
+    let process = if pid == 0 {
+        current!()
+    } else {
+        process_table::get_process(pid)
+            .ok_or_else(|| Error::with_message(Errno::ESRCH, "the process does not exist"))?
+    };
+    cgroup.attach(&process)
+}
+
+/// Parses `1` to freeze the cgroup or `0` to thaw it.
+fn write_freeze(cgroup: &Cgroup, input: &str) -> Result<()> {
+    let freeze = match input {
+        "0" => false,
+        "1" => true,
+        _ => return_errno_with_message!(Errno::EINVAL, "the value must be 0 or 1"),
+    };
+
+    cgroup.set_freeze(freeze);
+    Ok(())
+}
+
+/// Parses `$MAX $PERIOD`, where `$MAX` can be `max` and `$PERIOD` is optional.
+fn write_cpu_max(cgroup: &Cgroup, input: &str) -> Result<()> {
+    const MIN_QUOTA_US: u64 = 1000;
+    const MIN_PERIOD_US: u64 = 1000;
+    const MAX_PERIOD_US: u64 = 1_000_000;
+
+    let parse_us = |token: &str| {
+        token
+            .parse::<u64>()
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the time is invalid"))
+    };
+
+    let mut tokens = input.split_whitespace();
+    let quota_us = match tokens.next() {
+        Some("max") => None,
+        Some(token) => Some(parse_us(token)?),
+        None => return_errno_with_message!(Errno::EINVAL, "the quota is missing"),
+    };
+    let period_us = match tokens.next() {
+        Some(token) => parse_us(token)?,
+        None => cgroup.cpu_bandwidth().max().1,
+    };
+    if tokens.next().is_some() {
+        return_errno_with_message!(Errno::EINVAL, "too many fields are written");
+    }
+
+    if !(MIN_PERIOD_US..=MAX_PERIOD_US).contains(&period_us) {
+        return_errno_with_message!(Errno::EINVAL, "the period is out of range");
+    }
+    if quota_us.is_some_and(|quota_us| quota_us < MIN_QUOTA_US) {
+        return_errno_with_message!(Errno::EINVAL, "the quota is too small");
+    }
+
+    cgroup.cpu_bandwidth().set_max(quota_us, period_us);
+    Ok(())
+}
+
+/// Parses `max` or a number of bytes with an optional `K`, `M`, `G` or `T` suffix.
+fn write_memory_max(cgroup: &Cgroup, input: &str) -> Result<()> {
+    if input == "max" {
+        cgroup.set_memory_max(None);
+        return Ok(());
+    }
+
+    let (digits, shift) = match input.as_bytes().last() {
+        Some(b'k' | b'K') => (&input[..input.len() - 1], 10),
+        Some(b'm' | b'M') => (&input[..input.len() - 1], 20),
+        Some(b'g' | b'G') => (&input[..input.len() - 1], 30),
+        Some(b't' | b'T') => (&input[..input.len() - 1], 40),
+        _ => (input, 0),
+    };
+    let max = digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|value| value.checked_mul(1 << shift))
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the memory size is invalid"))?;
+
+    // Like Linux, the limit is rounded down to the page size.
+    cgroup.set_memory_max(Some(max / PAGE_SIZE * PAGE_SIZE));
+    Ok(())
+}
@@ -0,0 +1,57 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use super::inode::CgroupInode;
+use crate::{
+    fs::utils::{FileSystem, FsFlags, Inode, SuperBlock, NAME_MAX},
+    prelude::*,
+    process::cgroup::Cgroup,
+};
+
+/// The cgroup v2 file system.
+///
+/// Each directory in the file system corresponds to a cgroup, and the files in the directory
+/// are the interface files of the cgroup. Creating and removing the directories creates and
+/// removes the cgroups.
+pub struct CgroupFs {
+    sb: SuperBlock,
+    root: Arc<CgroupInode>,
+    inode_allocator: AtomicU64,
+}
+
+const MAGIC_NUMBER: u64 = 0x63677270; // CGROUP2_SUPER_MAGIC
+pub(super) const BLOCK_SIZE: usize = 4096;
+const ROOT_INO: u64 = 1;
+
+impl CgroupFs {
+    pub(super) fn new() -> Arc<Self> {
+        Arc::new_cyclic(|weak_fs| Self {
+            sb: SuperBlock::new(MAGIC_NUMBER, BLOCK_SIZE, NAME_MAX),
+            root: CgroupInode::new_dir(Cgroup::root().clone(), ROOT_INO, weak_fs.clone(), None),
+            inode_allocator: AtomicU64::new(ROOT_INO + 1),
+        })
+    }
+
+    pub(super) fn alloc_id(&self) -> u64 {
+        self.inode_allocator.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+impl FileSystem for CgroupFs {
+    fn sync(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+
+    fn sb(&self) -> SuperBlock {
+        self.sb.clone()
+    }
+
+    fn flags(&self) -> FsFlags {
+        FsFlags::empty()
+    }
+}
@@ -0,0 +1,346 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use super::{
+    control::ControlFile,
+    fs::{CgroupFs, BLOCK_SIZE},
+};
+use crate::{
+    fs::{
+        path::{is_dot, is_dotdot},
+        utils::{DirentVisitor, FileSystem, Inode, InodeMode, InodeType, Metadata},
+    },
+    prelude::*,
+    process::{cgroup::Cgroup, Gid, Uid},
+};
+
+/// An inode of the cgroup file system.
+///
+/// A directory inode corresponds to a cgroup, while a file inode corresponds to an interface
+/// file of the cgroup.
+pub struct CgroupInode {
+    kind: InodeKind,
+    metadata: RwLock<Metadata>,
+    fs: Weak<CgroupFs>,
+    this: Weak<CgroupInode>,
+}
+
+enum InodeKind {
+    Dir {
+        cgroup: Arc<Cgroup>,
+        parent: Option<Weak<CgroupInode>>,
+        /// The inodes that have been looked up, which are cached to keep their inode numbers.
+        cached_children: Mutex<BTreeMap<String, Arc<CgroupInode>>>,
+    },
+    File {
+        cgroup: Arc<Cgroup>,
+        file: ControlFile,
+    },
+}
+
+impl CgroupInode {
+    pub(super) fn new_dir(
+        cgroup: Arc<Cgroup>,
+        ino: u64,
+        fs: Weak<CgroupFs>,
+        parent: Option<Weak<CgroupInode>>,
+    ) -> Arc<Self> {
+        let kind = InodeKind::Dir {
+            cgroup,
+            parent,
+            cached_children: Mutex::new(BTreeMap::new()),
+        };
+        let metadata = Metadata::new_dir(ino, InodeMode::from_bits_truncate(0o755), BLOCK_SIZE);
+        Self::new(kind, metadata, fs)
+    }
+
+    fn new_file(cgroup: Arc<Cgroup>, file: ControlFile, ino: u64, fs: Weak<CgroupFs>) -> Arc<Self> {
+        let mode = if file.is_writable() { 0o644 } else { 0o444 };
+        let kind = InodeKind::File { cgroup, file };
+        let metadata = Metadata::new_file(ino, InodeMode::from_bits_truncate(mode), BLOCK_SIZE);
+        Self::new(kind, metadata, fs)
+    }
+
+    fn new(kind: InodeKind, metadata: Metadata, fs: Weak<CgroupFs>) -> Arc<Self> {
+        Arc::new_cyclic(|this| Self {
+            kind,
+            metadata: RwLock::new(metadata),
+            fs,
+            this: this.clone(),
+        })
+    }
+
+    fn this(&self) -> Arc<CgroupInode> {
+        self.this.upgrade().unwrap()
+    }
+
+    fn alloc_id(&self) -> u64 {
+        self.fs.upgrade().unwrap().alloc_id()
+    }
+
+    /// Returns the inode of a child, which is created if it has not been cached.
+    fn child(&self, name: &str) -> Result<Arc<CgroupInode>> {
+        let InodeKind::Dir {
+            cgroup,
+            cached_children,
+            ..
+        } = &self.kind
+        else {
+            return_errno_with_message!(Errno::ENOTDIR, "the inode is not a directory");
+        };
+
+        let mut cached_children = cached_children.lock();
+        if let Some(file) = ControlFile::lookup(cgroup, name) {
+            let inode = cached_children.entry(name.to_string()).or_insert_with(|| {
+                Self::new_file(cgroup.clone(), file, self.alloc_id(), self.fs.clone())
+            });
+            return Ok(inode.clone());
+        }
+
+        let Some(child_cgroup) = cgroup.child(name) else {
+            cached_children.remove(name);
+            return_errno_with_message!(Errno::ENOENT, "the cgroup does not exist");
+        };
+        if let Some(inode) = cached_children
+            .get(name)
+            .filter(|inode| Arc::ptr_eq(inode.cgroup(), &child_cgroup))
+        {
+            return Ok(inode.clone());
+        }
+
+        let inode = Self::new_dir(
+            child_cgroup,
+            self.alloc_id(),
+            self.fs.clone(),
+            Some(self.this.clone()),
+        );
+        cached_children.insert(name.to_string(), inode.clone());
+        Ok(inode)
+    }
+
+    fn cgroup(&self) -> &Arc<Cgroup> {
+        match &self.kind {
+            InodeKind::Dir { cgroup, .. } | InodeKind::File { cgroup, .. } => cgroup,
+        }
+    }
+
+    fn parent(&self) -> Option<Arc<CgroupInode>> {
+        match &self.kind {
+            InodeKind::Dir { parent, .. } => parent.as_ref().and_then(Weak::upgrade),
+            InodeKind::File { .. } => None,
+        }
+    }
+}
+
+impl Inode for CgroupInode {
+    fn size(&self) -> usize {
+        self.metadata.read().size
+    }
+
+    fn resize(&self, _new_size: usize) -> Result<()> {
+        // Writing to a cgroup file with `O_TRUNC` is allowed.
+        Ok(())
+    }
+
+    fn metadata(&self) -> Metadata {
+        *self.metadata.read()
+    }
+
+    fn ino(&self) -> u64 {
+        self.metadata.read().ino
+    }
+
+    fn type_(&self) -> InodeType {
+        self.metadata.read().type_
+    }
+
+    fn mode(&self) -> Result<InodeMode> {
+        Ok(self.metadata.read().mode)
+    }
+
+    fn set_mode(&self, mode: InodeMode) -> Result<()> {
+        self.metadata.write().mode = mode;
+        Ok(())
+    }
+
+    fn owner(&self) -> Result<Uid> {
+        Ok(self.metadata.read().uid)
+    }
+
+    fn set_owner(&self, uid: Uid) -> Result<()> {
+        self.metadata.write().uid = uid;
+        Ok(())
+    }
+
+    fn group(&self) -> Result<Gid> {
+        Ok(self.metadata.read().gid)
+    }
+
+    fn set_group(&self, gid: Gid) -> Result<()> {
+        self.metadata.write().gid = gid;
+        Ok(())
+    }
+
+    fn atime(&self) -> Duration {
+        self.metadata.read().atime
+    }
+
+    fn set_atime(&self, time: Duration) {
+        self.metadata.write().atime = time;
+    }
+
+    fn mtime(&self) -> Duration {
+        self.metadata.read().mtime
+    }
+
+    fn set_mtime(&self, time: Duration) {
+        self.metadata.write().mtime = time;
+    }
+
+    fn ctime(&self) -> Duration {
+        self.metadata.read().ctime
+    }
+
+    fn set_ctime(&self, time: Duration) {
+        self.metadata.write().ctime = time;
+    }
+
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let InodeKind::File { cgroup, file } = &self.kind else {
+            return_errno_with_message!(Errno::EISDIR, "the inode is a directory");
+        };
+
+        let output = file.read(cgroup);
+        let Some(data) = output.as_bytes().get(offset..) else {
+            return Ok(0);
+        };
+        Ok(writer.write_fallible(&mut data.into())?)
+    }
+
+    fn read_direct_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        self.read_at(offset, writer)
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let InodeKind::File { cgroup, file } = &self.kind else {
+            return_errno_with_message!(Errno::EISDIR, "the inode is a directory");
+        };
+
+        let len = reader.remain();
+        if len > PAGE_SIZE {
+            return_errno_with_message!(Errno::EINVAL, "the written contents are too long");
+        }
+        let mut buffer = vec![0u8; len];
+        reader
+            .read_fallible(&mut VmWriter::from(buffer.as_mut_slice()))
+            .map_err(|(err, _)| err)?;
+        let Ok(input) = core::str::from_utf8(&buffer) else {
+            return_errno_with_message!(Errno::EINVAL, "the written contents are not UTF-8");
+        };
+
+        file.write(cgroup, input)?;
+        Ok(len)
+    }
+
+    fn write_direct_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        self.write_at(offset, reader)
+    }
+
+    fn create(&self, name: &str, type_: InodeType, _mode: InodeMode) -> Result<Arc<dyn Inode>> {
+        let InodeKind::Dir { cgroup, .. } = &self.kind else {
+            return_errno_with_message!(Errno::ENOTDIR, "the inode is not a directory");
+        };
+        if type_ != InodeType::Dir {
+            return_errno_with_message!(Errno::EPERM, "only directories can be created");
+        }
+        if ControlFile::lookup(cgroup, name).is_some() {
+            return_errno_with_message!(Errno::EEXIST, "the name is used by a cgroup file");
+        }
+
+        cgroup.create_child(name)?;
+        Ok(self.child(name)?)
+    }
+
+    fn readdir_at(&self, offset: usize, visitor: &mut dyn DirentVisitor) -> Result<usize> {
+        let InodeKind::Dir { cgroup, .. } = &self.kind else {
+            return_errno_with_message!(Errno::ENOTDIR, "the inode is not a directory");
+        };
+
+        // The entries are listed by index: `.`, `..`, the cgroup files, and the child cgroups.
+        let mut names = vec![String::from("."), String::from("..")];
+        names.extend(ControlFile::all_of(cgroup).map(|file| file.name().to_string()));
+        names.extend(
+            cgroup
+                .children()
+                .iter()
+                .map(|child| child.name().to_string()),
+        );
+
+        let mut current_offset = offset;
+        for name in names.iter().skip(offset) {
+            let inode = if is_dot(name) {
+                self.this()
+            } else if is_dotdot(name) {
+                self.parent().unwrap_or_else(|| self.this())
+            } else {
+                // The child cgroup may have been removed concurrently.
+                let Ok(inode) = self.child(name) else {
+                    current_offset += 1;
+                    continue;
+                };
+                inode
+            };
+
+            if visitor
+                .visit(name, inode.ino(), inode.type_(), current_offset + 1)
+                .is_err()
+            {
+                break;
+            }
+            current_offset += 1;
+        }
+
+        if current_offset == offset && offset < names.len() {
+            return_errno_with_message!(Errno::EINVAL, "the buffer is too small");
+        }
+        Ok(current_offset - offset)
+    }
+
+    fn rmdir(&self, name: &str) -> Result<()> {
+        let InodeKind::Dir {
+            cgroup,
+            cached_children,
+            ..
+        } = &self.kind
+        else {
+            return_errno_with_message!(Errno::ENOTDIR, "the inode is not a directory");
+        };
+        if ControlFile::lookup(cgroup, name).is_some() {
+            return_errno_with_message!(Errno::ENOTDIR, "the cgroup file is not a directory");
+        }
+
+        cgroup.remove_child(name)?;
+        cached_children.lock().remove(name);
+        Ok(())
+    }
+
+    fn unlink(&self, _name: &str) -> Result<()> {
+        return_errno_with_message!(Errno::EPERM, "the cgroup files cannot be removed");
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>> {
+        if is_dot(name) {
+            return Ok(self.this());
+        }
+        if is_dotdot(name) {
+            return Ok(self.parent().unwrap_or_else(|| self.this()));
+        }
+
+        Ok(self.child(name)?)
+    }
+
+    fn fs(&self) -> Arc<dyn FileSystem> {
+        self.fs.upgrade().unwrap()
+    }
+}
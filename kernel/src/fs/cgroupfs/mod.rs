@@ -0,0 +1,44 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The cgroup v2 file system.
+//!
+//! The file system exposes the hierarchy of [`Cgroup`]s to the user space. It is mounted at
+//! `/sys/fs/cgroup` during the boot and can also be mounted elsewhere with the `cgroup2` type.
+//! All the mounts share the same hierarchy.
+//!
+//! [`Cgroup`]: crate::process::cgroup::Cgroup
+
+mod control;
+mod fs;
+mod inode;
+mod systree_node;
+
+use spin::Once;
+
+pub(super) use self::systree_node::MountPointNode;
+pub use self::{fs::CgroupFs, inode::CgroupInode};
+use crate::prelude::*;
+
+static CGROUPFS_SINGLETON: Once<Arc<CgroupFs>> = Once::new();
+
+/// Returns a reference to the global cgroup file system. Panics if not initialized.
+pub fn singleton() -> &'static Arc<CgroupFs> {
+    CGROUPFS_SINGLETON.get().expect("CgroupFs not initialized")
+}
+
+/// Initializes the cgroup file system.
+///
+/// This also creates the `/sys/fs/cgroup` directory in sysfs as the mount point. So it should be
+/// called *after* `aster_systree::init()`.
+pub fn init() {
+    CGROUPFS_SINGLETON.call_once(|| {
+        let fs_node = MountPointNode::new("fs");
+        fs_node.add_child(MountPointNode::new("cgroup")).unwrap();
+        aster_systree::singleton()
+            .root()
+            .add_child(fs_node)
+            .unwrap();
+
+        CgroupFs::new()
+    });
+}
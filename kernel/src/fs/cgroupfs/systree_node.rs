@@ -0,0 +1,102 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::borrow::Cow;
+
+use aster_systree::{
+    Error as SysTreeError, Result as SysTreeResult, SysAttrSet, SysBranchNode, SysBranchNodeFields,
+    SysNode, SysNodeId, SysNodeType, SysObj, SysStr,
+};
+
+use crate::prelude::*;
+
+/// An empty branch node in the `SysTree`.
+///
+/// The node provides a directory in sysfs where another file system can be mounted (e.g.,
+/// `/sys/fs/cgroup` or `/sys/kernel/tracing`).
+#[derive(Debug)]
+pub(in crate::fs) struct MountPointNode {
+    fields: SysBranchNodeFields<dyn SysObj>,
+    this: Weak<MountPointNode>,
+}
+
+impl MountPointNode {
+    pub(in crate::fs) fn new(name: &'static str) -> Arc<Self> {
+        let fields = SysBranchNodeFields::new(Cow::Borrowed(name), SysAttrSet::new_empty());
+        Arc::new_cyclic(|this| Self {
+            fields,
+            this: this.clone(),
+        })
+    }
+
+    pub(in crate::fs) fn add_child(&self, child: Arc<dyn SysObj>) -> SysTreeResult<()> {
+        self.fields.add_child(child)
+    }
+}
+
+impl SysObj for MountPointNode {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn arc_as_node(&self) -> Option<Arc<dyn SysNode>> {
+        self.this.upgrade().map(|this| this as Arc<dyn SysNode>)
+    }
+
+    fn arc_as_branch(&self) -> Option<Arc<dyn SysBranchNode>> {
+        self.this
+            .upgrade()
+            .map(|this| this as Arc<dyn SysBranchNode>)
+    }
+
+    fn id(&self) -> &SysNodeId {
+        self.fields.id()
+    }
+
+    fn type_(&self) -> SysNodeType {
+        SysNodeType::Branch
+    }
+
+    fn name(&self) -> SysStr {
+        Cow::Owned(self.fields.name().to_string())
+    }
+}
+
+impl SysNode for MountPointNode {
+    fn node_attrs(&self) -> &SysAttrSet {
+        self.fields.attr_set()
+    }
+
+    fn read_attr(&self, _name: &str, _writer: &mut VmWriter) -> SysTreeResult<usize> {
+        Err(SysTreeError::AttributeError)
+    }
+
+    fn write_attr(&self, _name: &str, _reader: &mut VmReader) -> SysTreeResult<usize> {
+        Err(SysTreeError::AttributeError)
+    }
+}
+
+impl SysBranchNode for MountPointNode {
+    fn visit_child_with(&self, name: &str, f: &mut dyn FnMut(Option<&dyn SysNode>)) {
+        let children = self.fields.children.read();
+        match children.get(name).and_then(|child| child.arc_as_node()) {
+            Some(child) => f(Some(child.as_ref())),
+            None => f(None),
+        }
+    }
+
+    fn visit_children_with(&self, min_id: u64, f: &mut dyn FnMut(&Arc<dyn SysObj>) -> Option<()>) {
+        let children = self.fields.children.read();
+        for child in children
+            .values()
+            .filter(|child| child.id().as_u64() >= min_id)
+        {
+            if f(child).is_none() {
+                break;
+            }
+        }
+    }
+
+    fn child(&self, name: &str) -> Option<Arc<dyn SysObj>> {
+        self.fields.children.read().get(name).cloned()
+    }
+}
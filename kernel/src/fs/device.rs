@@ -33,7 +33,7 @@ impl Debug for dyn Device {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Device type
 pub enum DeviceType {
     CharDevice,
@@ -3,6 +3,7 @@
 use alloc::{
     collections::vec_deque::VecDeque,
     sync::{Arc, Weak},
+    vec::Vec,
 };
 use core::sync::atomic::{AtomicBool, Ordering};
 
@@ -63,13 +64,18 @@ struct Inner {
 
 impl Entry {
     /// Creates a new epoll entry associated with the given epoll file.
+    ///
+    /// If `is_exclusive` is true, the entry will be woken up exclusively (i.e., `EPOLLEXCLUSIVE`).
+    /// This cannot be changed after the entry is created.
     pub(super) fn new(
         fd: FileDesc,
         file: KeyableWeak<dyn FileLike>,
         ready_set: Arc<ReadySet>,
+        is_exclusive: bool,
     ) -> Arc<Self> {
         Arc::new_cyclic(|me| {
             let observer = Arc::new(Observer::new(ready_set, me.clone()));
+            let observer_weak = Arc::downgrade(&observer) as _;
 
             let inner = Inner {
                 event: EpollEvent {
@@ -77,7 +83,11 @@ impl Entry {
                     user_data: 0,
                 },
                 flags: EpollFlags::empty(),
-                poller: PollHandle::new(Arc::downgrade(&observer) as _),
+                poller: if is_exclusive {
+                    PollHandle::new_exclusive(observer_weak)
+                } else {
+                    PollHandle::new(observer_weak)
+                },
             };
 
             Self {
@@ -92,7 +102,7 @@ impl Entry {
     ///
     /// Since an epoll entry only holds a weak reference to the file,
     /// it is possible (albeit unlikely) that the file has been dropped.
-    fn file(&self) -> Option<Arc<dyn FileLike>> {
+    pub(super) fn file(&self) -> Option<Arc<dyn FileLike>> {
         self.key.file.upgrade().map(KeyableArc::into)
     }
 
@@ -137,6 +147,22 @@ impl Entry {
         Some((ep_event, is_still_ready))
     }
 
+    /// Checks whether the file associated with this epoll entry has some events.
+    ///
+    /// Unlike [`Self::poll`], this method has no side effects on the entry.
+    fn has_events(&self) -> bool {
+        let Some(file) = self.file() else {
+            return false;
+        };
+        let inner = self.inner.lock();
+
+        if !self.observer.is_enabled() {
+            return false;
+        }
+
+        !file.poll(inner.event.events, None).is_empty()
+    }
+
     /// Updates the epoll entry by the given event masks and flags.
     ///
     /// This method needs to be called in response to `EpollCtl::Add` and `EpollCtl::Mod`.
@@ -153,6 +179,11 @@ impl Entry {
         file.poll(event.events, Some(&mut inner.poller))
     }
 
+    /// Returns whether the epoll entry is woken up exclusively.
+    pub(super) fn is_exclusive(&self) -> bool {
+        self.inner.lock().flags.contains(EpollFlags::EXCLUSIVE)
+    }
+
     /// Shuts down the epoll entry.
     ///
     /// This method needs to be called in response to `EpollCtl::Del`.
@@ -269,6 +300,20 @@ impl events::Observer<IoEvents> for Observer {
     fn on_events(&self, _events: &IoEvents) {
         self.ready_set.push(self);
     }
+
+    fn on_exclusive_events(&self, _events: &IoEvents) -> bool {
+        // A disabled entry (e.g., a one-shot entry that has reported its events) does not take
+        // the events, so that they can be delivered to other exclusive entries.
+        if !self.is_enabled() {
+            return false;
+        }
+
+        self.ready_set.push(self);
+
+        // Following Linux, the events are taken only if someone is waiting for the epoll file.
+        // Otherwise, they are also delivered to other exclusive entries.
+        self.ready_set.has_waiters()
+    }
 }
 
 /// A set of ready epoll entries.
@@ -326,15 +371,28 @@ impl ReadySet {
         }
     }
 
+    /// Returns whether someone is waiting for the events of the epoll file.
+    fn has_waiters(&self) -> bool {
+        self.pollee.has_pollers()
+    }
+
     pub(super) fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
         self.pollee
             .poll_with(mask, poller, || self.check_io_events())
     }
 
     fn check_io_events(&self) -> IoEvents {
-        let entries = self.entries.lock();
-
-        if !entries.is_empty() {
+        // The entries are only probably ready. We need to check them, so that the epoll file is
+        // not reported as readable spuriously when it is polled (e.g., by another epoll file).
+        //
+        // Note that we cannot poll the files while holding the spin lock.
+        let entries: Vec<Weak<Entry>> = self.entries.lock().iter().cloned().collect();
+
+        if entries
+            .iter()
+            .filter_map(Weak::upgrade)
+            .any(|entry| entry.has_events())
+        {
             IoEvents::IN
         } else {
             IoEvents::empty()
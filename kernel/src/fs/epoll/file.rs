@@ -1,6 +1,9 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::{collections::btree_set::BTreeSet, sync::Arc};
+use alloc::{
+    collections::btree_set::BTreeSet,
+    sync::{Arc, Weak},
+};
 use core::{borrow::Borrow, time::Duration};
 
 use keyable_arc::KeyableWeak;
@@ -44,14 +47,31 @@ pub struct EpollFile {
     // Keep this in a separate `Arc` to avoid dropping `EpollFile` in the observer callback, which
     // may cause deadlocks.
     ready: Arc<ReadySet>,
+    // The epoll files that may contain this epoll file in their interest lists.
+    //
+    // This is used to find the nesting depth of this epoll file. The epoll files that no longer
+    // contain this epoll file are removed lazily.
+    parents: Mutex<Vec<Weak<EpollFile>>>,
+    weak_self: Weak<EpollFile>,
 }
 
+/// The maximum nesting depth of epoll files, excluding the outermost one.
+///
+/// This follows Linux's `EP_MAX_NESTS`.
+const MAX_NESTING_DEPTH: usize = 4;
+
+/// A lock that serializes adding epoll files to the interest lists, so that the nesting checks
+/// do not race with each other.
+static NESTING_LOCK: Mutex<()> = Mutex::new(());
+
 impl EpollFile {
     /// Creates a new epoll file.
     pub fn new() -> Arc<Self> {
-        Arc::new(Self {
+        Arc::new_cyclic(|weak_self| Self {
             interest: Mutex::new(BTreeSet::new()),
             ready: Arc::new(ReadySet::new()),
+            parents: Mutex::new(Vec::new()),
+            weak_self: weak_self.clone(),
         })
     }
 
@@ -67,7 +87,14 @@ impl EpollFile {
         let file = get_file_fast!(&mut file_table, fd).into_owned();
         drop(file_table);
 
+        if core::ptr::addr_eq(Arc::as_ptr(&file), self as *const Self) {
+            return_errno_with_message!(Errno::EINVAL, "the epoll file cannot monitor itself");
+        }
+
         match *cmd {
+            EpollCtl::Add(fd, ep_event, ep_flags) if file.downcast_ref::<EpollFile>().is_some() => {
+                self.add_nested_interest(fd, file, ep_event, ep_flags)
+            }
             EpollCtl::Add(fd, ep_event, ep_flags) => {
                 self.add_interest(fd, file, ep_event, ep_flags)
             }
@@ -87,6 +114,11 @@ impl EpollFile {
     ) -> Result<()> {
         self.warn_unsupported_flags(&ep_flags);
 
+        let is_exclusive = ep_flags.contains(EpollFlags::EXCLUSIVE);
+        if is_exclusive {
+            check_exclusive_entry(&file, &ep_event, &ep_flags)?;
+        }
+
         // Add the new entry to the interest list and start monitoring its events
         let ready_entry = {
             let mut interest = self.interest.lock();
@@ -98,7 +130,12 @@ impl EpollFile {
                 );
             }
 
-            let entry = Entry::new(fd, Arc::downgrade(&file).into(), self.ready.clone());
+            let entry = Entry::new(
+                fd,
+                Arc::downgrade(&file).into(),
+                self.ready.clone(),
+                is_exclusive,
+            );
             let events = entry.update(ep_event, ep_flags);
 
             let ready_entry = if !events.is_empty() {
@@ -121,6 +158,91 @@ impl EpollFile {
         Ok(())
     }
 
+    /// Adds an epoll file to the interest list.
+    ///
+    /// This fails with [`Errno::ELOOP`] if the epoll files would monitor each other in a loop, or
+    /// the epoll files would be nested too deeply.
+    fn add_nested_interest(
+        &self,
+        fd: FileDesc,
+        file: Arc<dyn FileLike>,
+        ep_event: EpollEvent,
+        ep_flags: EpollFlags,
+    ) -> Result<()> {
+        let _nesting_guard = NESTING_LOCK.lock();
+
+        let child = file.downcast_ref::<EpollFile>().unwrap();
+        let depth_below = child.depth_below(self, 0)?;
+        if depth_below + 1 + self.depth_above() > MAX_NESTING_DEPTH {
+            return_errno_with_message!(Errno::ELOOP, "the epoll files are nested too deeply");
+        }
+
+        self.add_interest(fd, file.clone(), ep_event, ep_flags)?;
+        child.parents.lock().push(self.weak_self.clone());
+
+        Ok(())
+    }
+
+    /// Returns the maximum number of the epoll files nested in this one at any level.
+    ///
+    /// This fails with [`Errno::ELOOP`] if `ancestor` is nested in this epoll file, or the
+    /// depth exceeds the maximum one.
+    fn depth_below(&self, ancestor: &EpollFile, depth: usize) -> Result<usize> {
+        if core::ptr::eq(self, ancestor) {
+            return_errno_with_message!(Errno::ELOOP, "the epoll files would monitor each other");
+        }
+        if depth > MAX_NESTING_DEPTH {
+            return_errno_with_message!(Errno::ELOOP, "the epoll files are nested too deeply");
+        }
+
+        let children = self.nested_files();
+        let mut max_depth = 0;
+        for child in children.iter() {
+            let child = child.downcast_ref::<EpollFile>().unwrap();
+            max_depth = max_depth.max(child.depth_below(ancestor, depth + 1)? + 1);
+        }
+        Ok(max_depth)
+    }
+
+    /// Returns the maximum number of the epoll files that contain this one at any level.
+    fn depth_above(&self) -> usize {
+        let parents = {
+            let mut parents = self.parents.lock();
+            parents.retain(|parent| {
+                parent
+                    .upgrade()
+                    .is_some_and(|parent| parent.contains_file(self))
+            });
+            parents.clone()
+        };
+
+        parents
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(|parent| parent.depth_above() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the epoll files in the interest list.
+    fn nested_files(&self) -> Vec<Arc<dyn FileLike>> {
+        self.interest
+            .lock()
+            .iter()
+            .filter_map(|EntryHolder(entry)| entry.file())
+            .filter(|file| file.downcast_ref::<EpollFile>().is_some())
+            .collect()
+    }
+
+    /// Returns whether the file is in the interest list.
+    fn contains_file(&self, file: &EpollFile) -> bool {
+        self.interest.lock().iter().any(|EntryHolder(entry)| {
+            entry
+                .file()
+                .is_some_and(|entry_file| core::ptr::addr_eq(Arc::as_ptr(&entry_file), file))
+        })
+    }
+
     fn del_interest(&self, fd: FileDesc, file: KeyableWeak<dyn FileLike>) -> Result<()> {
         // If this epoll entry is in the ready list, then we should delete it.
         // But unfortunately, deleting an entry from the ready list has a
@@ -147,6 +269,13 @@ impl EpollFile {
     ) -> Result<()> {
         self.warn_unsupported_flags(&new_ep_flags);
 
+        if new_ep_flags.contains(EpollFlags::EXCLUSIVE) {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the exclusive wakeup mode can only be set when adding the file"
+            );
+        }
+
         // Update the epoll entry
         let ready_entry = {
             let interest = self.interest.lock();
@@ -155,6 +284,12 @@ impl EpollFile {
                 interest.get(&EntryKey::from((fd, &file))).ok_or_else(|| {
                     Error::with_message(Errno::ENOENT, "the file is not in the interest list")
                 })?;
+            if entry.is_exclusive() {
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "the file is monitored in the exclusive wakeup mode"
+                );
+            }
             let events = entry.update(new_ep_event, new_ep_flags);
 
             if !events.is_empty() {
@@ -185,7 +320,9 @@ impl EpollFile {
     pub fn wait(&self, max_events: usize, timeout: Option<&Duration>) -> Result<Vec<EpollEvent>> {
         let mut ep_events = Vec::new();
 
-        self.wait_events(IoEvents::IN, timeout, || {
+        // Threads waiting on the same epoll file are woken up one by one to avoid the thundering
+        // herd problem.
+        self.wait_events_exclusive(IoEvents::IN, timeout, || {
             self.pop_multi_ready(max_events, &mut ep_events);
 
             if ep_events.is_empty() {
@@ -240,12 +377,43 @@ impl EpollFile {
     }
 
     fn warn_unsupported_flags(&self, flags: &EpollFlags) {
-        if flags.intersects(EpollFlags::EXCLUSIVE | EpollFlags::WAKE_UP) {
+        if flags.intersects(EpollFlags::WAKE_UP) {
             warn!("{:?} contains unsupported flags", flags);
         }
     }
 }
 
+/// Checks whether the file can be monitored in the exclusive wakeup mode (i.e., `EPOLLEXCLUSIVE`).
+fn check_exclusive_entry(
+    file: &Arc<dyn FileLike>,
+    ep_event: &EpollEvent,
+    ep_flags: &EpollFlags,
+) -> Result<()> {
+    const EXCLUSIVE_OK_EVENTS: IoEvents = IoEvents::IN
+        .union(IoEvents::OUT)
+        .union(IoEvents::ERR)
+        .union(IoEvents::HUP);
+    const EXCLUSIVE_OK_FLAGS: EpollFlags = EpollFlags::EXCLUSIVE
+        .union(EpollFlags::EDGE_TRIGGER)
+        .union(EpollFlags::WAKE_UP);
+
+    if !EXCLUSIVE_OK_EVENTS.contains(ep_event.events) || !EXCLUSIVE_OK_FLAGS.contains(*ep_flags) {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the events or flags are not allowed in the exclusive wakeup mode"
+        );
+    }
+
+    if file.downcast_ref::<EpollFile>().is_some() {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "epoll files cannot be monitored in the exclusive wakeup mode"
+        );
+    }
+
+    Ok(())
+}
+
 impl Pollable for EpollFile {
     fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
         self.ready.poll(mask, poller)
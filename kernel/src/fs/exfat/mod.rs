@@ -104,6 +104,7 @@ mod test {
             BlockDeviceMeta {
                 max_nr_segments_per_bio: usize::MAX,
                 nr_sectors: self.sectors_count(),
+                max_nr_sectors_per_discard: usize::MAX,
             }
         }
     }
@@ -233,14 +233,14 @@ impl BlockGroup {
         let mut bio_waiter = BioWaiter::new();
         // Writes back the inode bitmap.
         let inode_bitmap_bid = Bid::new(inner.metadata.descriptor.inode_bitmap_bid as u64);
-        bio_waiter.concat(fs.block_device().write_bytes_async(
+        bio_waiter.concat(fs.write_metadata_bytes_async(
             inode_bitmap_bid.to_offset(),
             inner.metadata.inode_bitmap.as_bytes(),
         )?);
 
         // Writes back the block bitmap.
         let block_bitmap_bid = Bid::new(inner.metadata.descriptor.block_bitmap_bid as u64);
-        bio_waiter.concat(fs.block_device().write_bytes_async(
+        bio_waiter.concat(fs.write_metadata_bytes_async(
             block_bitmap_bid.to_offset(),
             inner.metadata.block_bitmap.as_bytes(),
         )?);
@@ -256,6 +256,9 @@ impl BlockGroup {
 
     /// Writes back all of the cached inodes.
     ///
+    /// The raw inode metadata is only written to the raw inode metadata cache. Use
+    /// [`Self::write_back_raw_inodes`] to write it back to the block device.
+    ///
     /// The `sync_all` method of inode may modify the data of this block group,
     /// so we should not hold the lock while syncing the inodes.
     pub fn sync_all_inodes(&self) -> Result<()> {
@@ -288,14 +291,26 @@ impl BlockGroup {
             inode.sync_all()?;
         }
         drop(remaining_inodes);
+        Ok(())
+    }
 
-        // Writes back the raw inode metadata.
+    /// Writes back the raw inode metadata of all the inodes to the block device.
+    pub fn write_back_raw_inodes(&self) -> Result<()> {
         self.raw_inodes_cache
             .pages()
             .decommit(0..self.bg_impl.raw_inodes_size)?;
         Ok(())
     }
 
+    /// Writes back the block of the inode table that contains the raw inode metadata to the
+    /// block device.
+    pub fn write_back_raw_inode(&self, inode_idx: u32) -> Result<()> {
+        let inode_size = self.fs().inode_size();
+        let offset = (inode_idx as usize) * inode_size;
+        self.raw_inodes_cache
+            .evict_range(offset..offset + inode_size)
+    }
+
     fn fs(&self) -> Arc<Ext2> {
         self.bg_impl.fs.upgrade().unwrap()
     }
@@ -344,7 +359,7 @@ impl PageCacheBackend for BlockGroupImpl {
         self.fs
             .upgrade()
             .unwrap()
-            .write_blocks_async(bid, bio_segment)
+            .write_metadata_blocks_async(bid, bio_segment)
     }
 
     fn npages(&self) -> usize {
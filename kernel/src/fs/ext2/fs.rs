@@ -6,6 +6,7 @@ use super::{
     block_group::{BlockGroup, RawGroupDescriptor},
     block_ptr::Ext2Bid,
     inode::{FilePerm, Inode, InodeDesc, RawInode},
+    journal::Journal,
     prelude::*,
     super_block::{RawSuperBlock, SuperBlock, SUPER_BLOCK_OFFSET},
 };
@@ -24,12 +25,42 @@ pub struct Ext2 {
     inode_size: usize,
     block_size: usize,
     group_descriptors_segment: USegment,
+    journal: Option<Journal>,
     self_ref: Weak<Self>,
 }
 
 impl Ext2 {
     /// Opens and loads an Ext2 from the `block_device`.
+    ///
+    /// If the Ext2 has a journal, the committed transactions in the journal are replayed
+    /// before the Ext2 is loaded.
     pub fn open(block_device: Arc<dyn BlockDevice>) -> Result<Arc<Self>> {
+        let ext2 = Self::load(block_device.clone(), None)?;
+        let super_block = **ext2.super_block();
+        let Some(journal_ino) = super_block.journal_ino() else {
+            if super_block.needs_recovery() {
+                return_errno_with_message!(Errno::EINVAL, "the external journal is not supported");
+            }
+            return Ok(ext2);
+        };
+
+        // The metadata may be out of date before the recovery, so the Ext2 is loaded again.
+        let journal = Journal::load(&ext2, journal_ino, block_device.clone())?;
+        drop(ext2);
+        journal.recover()?;
+        let ext2 = Self::load(block_device, Some(journal))?;
+
+        // The Ext2 is never unmounted cleanly, so it is marked as in use until the next
+        // recovery.
+        if !ext2.super_block().needs_recovery() {
+            ext2.super_block.write().set_needs_recovery();
+            ext2.sync_metadata()?;
+        }
+        Ok(ext2)
+    }
+
+    /// Loads an Ext2 from the `block_device` with the `journal`.
+    fn load(block_device: Arc<dyn BlockDevice>, journal: Option<Journal>) -> Result<Arc<Self>> {
         // Load the superblock
         // TODO: if the main superblock is corrupted, should we load the backup?
         let super_block = {
@@ -94,6 +125,7 @@ impl Ext2 {
             block_device,
             super_block: RwMutex::new(Dirty::new(super_block)),
             group_descriptors_segment,
+            journal,
             self_ref: weak_ref.clone(),
         });
         Ok(ext2)
@@ -295,32 +327,67 @@ impl Ext2 {
             current_range.start += range_in_group.len() as Ext2Bid
         }
 
+        // The freed blocks may be reused as data blocks, which must not be overwritten by
+        // their stale contents in the journal.
+        if let Some(journal) = self.journal.as_ref() {
+            journal.forget_blocks(range);
+        }
         Ok(())
     }
 
     /// Reads contiguous blocks starting from the `bid` synchronously.
     pub(super) fn read_blocks(&self, bid: Ext2Bid, bio_segment: BioSegment) -> Result<()> {
+        let journaled_blocks = self.journaled_blocks(bid, &bio_segment);
         let status = self
             .block_device
-            .read_blocks(Bid::new(bid as u64), bio_segment)?;
+            .read_blocks(Bid::new(bid as u64), bio_segment.clone())?;
         match status {
-            BioStatus::Complete => Ok(()),
+            BioStatus::Complete => {
+                for (journaled_bid, block) in journaled_blocks {
+                    let offset = (journaled_bid - bid) as usize * BLOCK_SIZE;
+                    bio_segment.write_bytes(offset, &block)?;
+                }
+                Ok(())
+            }
             err_status => Err(Error::from(err_status)),
         }
     }
 
     /// Reads contiguous blocks starting from the `bid` asynchronously.
+    ///
+    /// If some of the blocks are in the journal but not written to their locations yet, the
+    /// blocks are read synchronously.
     pub(super) fn read_blocks_async(
         &self,
         bid: Ext2Bid,
         bio_segment: BioSegment,
     ) -> Result<BioWaiter> {
+        if let Some(journal) = self.journal.as_ref()
+            && journal.contains_blocks(bid..bid + bio_segment.nblocks() as Ext2Bid)
+        {
+            self.read_blocks(bid, bio_segment)?;
+            return Ok(BioWaiter::new());
+        }
+
         let waiter = self
             .block_device
             .read_blocks_async(Bid::new(bid as u64), bio_segment)?;
         Ok(waiter)
     }
 
+    /// Returns the latest contents of the blocks that are in the journal but not written to
+    /// their locations yet.
+    fn journaled_blocks(
+        &self,
+        bid: Ext2Bid,
+        bio_segment: &BioSegment,
+    ) -> Vec<(Ext2Bid, Box<[u8]>)> {
+        match self.journal.as_ref() {
+            Some(journal) => journal.find_blocks(bid..bid + bio_segment.nblocks() as Ext2Bid),
+            None => Vec::new(),
+        }
+    }
+
     /// Writes contiguous blocks starting from the `bid` synchronously.
     pub(super) fn write_blocks(&self, bid: Ext2Bid, bio_segment: BioSegment) -> Result<()> {
         let status = self
@@ -344,8 +411,70 @@ impl Ext2 {
         Ok(waiter)
     }
 
+    /// Writes contiguous metadata blocks starting from the `bid` synchronously.
+    pub(super) fn write_metadata_blocks(
+        &self,
+        bid: Ext2Bid,
+        bio_segment: BioSegment,
+    ) -> Result<()> {
+        match self.write_metadata_blocks_async(bid, bio_segment)?.wait() {
+            Some(BioStatus::Complete) => Ok(()),
+            _ => return_errno!(Errno::EIO),
+        }
+    }
+
+    /// Writes contiguous metadata blocks starting from the `bid` asynchronously.
+    ///
+    /// If there is a journal, the blocks are written to the running transaction, and are
+    /// written to their locations after the transaction is committed.
+    pub(super) fn write_metadata_blocks_async(
+        &self,
+        bid: Ext2Bid,
+        bio_segment: BioSegment,
+    ) -> Result<BioWaiter> {
+        match self.journal.as_ref() {
+            Some(journal) => {
+                journal.write_blocks(bid, &bio_segment)?;
+                Ok(BioWaiter::new())
+            }
+            None => self.write_blocks_async(bid, bio_segment),
+        }
+    }
+
+    /// Writes the metadata bytes at the `offset` of the block device asynchronously.
+    ///
+    /// The `offset` and the length of `buf` must be aligned to the sector size. If there is a
+    /// journal, the bytes are written in the same way as [`Self::write_metadata_blocks_async`].
+    pub(super) fn write_metadata_bytes_async(
+        &self,
+        offset: usize,
+        buf: &[u8],
+    ) -> Result<BioWaiter> {
+        match self.journal.as_ref() {
+            Some(journal) => {
+                journal.write_bytes(offset, buf)?;
+                Ok(BioWaiter::new())
+            }
+            None => Ok(self.block_device.write_bytes_async(offset, buf)?),
+        }
+    }
+
+    /// Commits the running transaction of the journal, if there is a journal.
+    fn commit(&self) -> Result<()> {
+        match self.journal.as_ref() {
+            Some(journal) => journal.commit(),
+            None => Ok(()),
+        }
+    }
+
     /// Writes back the metadata to the block device.
     pub fn sync_metadata(&self) -> Result<()> {
+        self.write_back_metadata()?;
+        self.commit()
+    }
+
+    /// Writes back the bitmaps, the group descriptors, and the superblocks.
+    fn write_back_metadata(&self) -> Result<()> {
         // If the superblock is clean, the block groups must be clean.
         if !self.super_block.read().is_dirty() {
             return Ok(());
@@ -361,15 +490,14 @@ impl Ext2 {
         let mut bio_waiter = BioWaiter::new();
         let raw_super_block = RawSuperBlock::from((*super_block).deref());
         bio_waiter.concat(
-            self.block_device
-                .write_bytes_async(SUPER_BLOCK_OFFSET, raw_super_block.as_bytes())?,
+            self.write_metadata_bytes_async(SUPER_BLOCK_OFFSET, raw_super_block.as_bytes())?,
         );
         let group_descriptors_bio_segment = BioSegment::new_from_segment(
             self.group_descriptors_segment.clone(),
             BioDirection::ToDevice,
         );
-        bio_waiter.concat(self.block_device.write_blocks_async(
-            super_block.group_descriptors_bid(0),
+        bio_waiter.concat(self.write_metadata_blocks_async(
+            super_block.group_descriptors_bid(0).to_raw() as Ext2Bid,
             group_descriptors_bio_segment.clone(),
         )?);
         bio_waiter
@@ -383,12 +511,12 @@ impl Ext2 {
             if super_block.is_backup_group(idx as usize) {
                 let mut bio_waiter = BioWaiter::new();
                 raw_super_block_backup.block_group_idx = idx as u16;
-                bio_waiter.concat(self.block_device.write_bytes_async(
+                bio_waiter.concat(self.write_metadata_bytes_async(
                     super_block.bid(idx as usize).to_offset(),
                     raw_super_block_backup.as_bytes(),
                 )?);
-                bio_waiter.concat(self.block_device.write_blocks_async(
-                    super_block.group_descriptors_bid(idx as usize),
+                bio_waiter.concat(self.write_metadata_blocks_async(
+                    super_block.group_descriptors_bid(idx as usize).to_raw() as Ext2Bid,
                     group_descriptors_bio_segment.clone(),
                 )?);
                 bio_waiter.wait().ok_or_else(|| {
@@ -403,11 +531,36 @@ impl Ext2 {
     }
 
     /// Writes back all the cached inodes to the block device.
+    ///
+    /// If there is a journal, the metadata are committed to the journal as a transaction after
+    /// the data of the files are written to their locations, i.e., in the ordered mode of Ext3.
+    ///
+    /// Otherwise, the writes are ordered so that a crash does not leave an inode on the device
+    /// referencing blocks that are not allocated: the data and the indirect blocks are written
+    /// first, then the bitmaps, the group descriptors, and the superblock that record the
+    /// allocations, and finally the inode tables.
     pub fn sync_all_inodes(&self) -> Result<()> {
         for block_group in &self.block_groups {
             block_group.sync_all_inodes()?;
         }
-        Ok(())
+        self.write_back_metadata()?;
+        for block_group in &self.block_groups {
+            block_group.write_back_raw_inodes()?;
+        }
+        self.commit()
+    }
+
+    /// Writes back the inode to the block device.
+    ///
+    /// The writes are ordered in the same way as [`Self::sync_all_inodes`], but only the
+    /// block of the inode table that contains the inode is written.
+    pub(super) fn write_back_inode(&self, inode: &Inode) -> Result<()> {
+        inode.sync_all()?;
+        self.write_back_metadata()?;
+
+        let (_, block_group) = self.block_group_of_ino(inode.ino())?;
+        block_group.write_back_raw_inode(self.inode_idx(inode.ino()))?;
+        self.commit()
     }
 
     fn block_group_of_bid(&self, bid: Ext2Bid) -> Result<(usize, &BlockGroup)> {
@@ -7,7 +7,9 @@ use core::time::Duration;
 use aster_rights::Full;
 
 use crate::{
+    device::get_device,
     fs::{
+        device::{Device, DeviceType},
         ext2::{FilePerm, Inode as Ext2Inode},
         utils::{
             DirentVisitor, Extension, FallocMode, FileSystem, Inode, InodeMode, InodeType,
@@ -123,12 +125,18 @@ impl Inode for Ext2Inode {
                 inode.set_device_id(dev.id().into()).unwrap();
                 inode
             }
-            _ => todo!(),
+            MknodType::NamedPipeNode => self.create(name, inode_type, mode.into())?,
         };
 
         Ok(inode)
     }
 
+    fn as_device(&self) -> Option<Arc<dyn Device>> {
+        // Only the device number is stored on the disk, so the device is looked up by it.
+        let type_ = DeviceType::try_from(self.inode_type()).ok()?;
+        get_device(type_, self.device_id() as usize).ok()
+    }
+
     fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>> {
         Ok(self.lookup(name)?)
     }
@@ -176,7 +184,7 @@ impl Inode for Ext2Inode {
     }
 
     fn sync_all(&self) -> Result<()> {
-        self.sync_all()?;
+        self.fs().write_back_inode(self)?;
         self.fs().block_device().sync()?;
         Ok(())
     }
@@ -749,6 +749,22 @@ impl Inode {
         Ok(bytes_written)
     }
 
+    /// Returns the ranges of the device blocks that the blocks in `range` of the file are
+    /// mapped to.
+    ///
+    /// # Panics
+    ///
+    /// If the `range` is empty, this method will panic.
+    pub(super) fn device_ranges(&self, range: Range<Ext2Bid>) -> Result<Vec<Range<Ext2Bid>>> {
+        let inner = self.inner.read();
+        let mut reader = DeviceRangeReader::new(&inner.inode_impl.block_manager, range)?;
+        let mut device_ranges = Vec::new();
+        while !reader.range.is_empty() {
+            device_ranges.push(reader.read()?);
+        }
+        Ok(device_ranges)
+    }
+
     pub fn sync_all(&self) -> Result<()> {
         let mut inner = self.inner.write();
         inner.sync_data()?;
@@ -1203,6 +1219,7 @@ impl InodeImpl {
             nblocks: AtomicUsize::new(desc.blocks_count() as _),
             block_ptrs: RwMutex::new(desc.block_ptrs),
             indirect_blocks: RwMutex::new(IndirectBlockCache::new(fs.clone())),
+            is_dir: desc.type_ == InodeType::Dir,
             fs,
         };
         Self {
@@ -1832,6 +1849,8 @@ struct InodeBlockManager {
     /// frequent reads access the `InodeDesc` copy without locking.
     block_ptrs: RwMutex<BlockPtrs>,
     indirect_blocks: RwMutex<IndirectBlockCache>,
+    /// Whether the blocks contain directory entries, which are metadata rather than data.
+    is_dir: bool,
     fs: Weak<Ext2>,
 }
 
@@ -1902,7 +1921,7 @@ impl InodeBlockManager {
             let bio_segment = BioSegment::alloc(range_nblocks, BioDirection::ToDevice);
             bio_segment.writer().unwrap().write_fallible(reader)?;
 
-            let waiter = self.fs().write_blocks_async(start_bid, bio_segment)?;
+            let waiter = self.write_device_blocks_async(start_bid, bio_segment)?;
             bio_waiter.concat(waiter);
         }
 
@@ -1927,13 +1946,27 @@ impl InodeBlockManager {
                 .writer()
                 .unwrap()
                 .write_fallible(&mut frame.reader().to_fallible())?;
-            let waiter = self.fs().write_blocks_async(start_bid, bio_segment)?;
+            let waiter = self.write_device_blocks_async(start_bid, bio_segment)?;
             bio_waiter.concat(waiter);
         }
 
         Ok(bio_waiter)
     }
 
+    /// Writes the blocks to the device, where the directory entries are written as metadata.
+    fn write_device_blocks_async(
+        &self,
+        device_bid: Ext2Bid,
+        bio_segment: BioSegment,
+    ) -> Result<BioWaiter> {
+        if self.is_dir {
+            self.fs()
+                .write_metadata_blocks_async(device_bid, bio_segment)
+        } else {
+            self.fs().write_blocks_async(device_bid, bio_segment)
+        }
+    }
+
     pub fn nblocks(&self) -> usize {
         self.nblocks.load(Ordering::Acquire)
     }
@@ -0,0 +1,625 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The journal of the metadata.
+//!
+//! The journal is in the format of JBD2, which is used by Ext3 and Ext4, so that the
+//! filesystem can also be recovered by Linux or `e2fsck`. It is in an inode of the filesystem,
+//! which is usually created by `mke2fs -O has_journal`.
+//!
+//! The metadata blocks written between two commits, i.e., the bitmaps, the group descriptors,
+//! the superblocks, the inode tables, the indirect blocks, the directory blocks, and the xattr
+//! blocks, are kept in memory as the running transaction. A transaction is committed in the
+//! ordered mode of Ext3:
+//! 1. The data blocks of the files are written to their locations before the commit;
+//! 2. The descriptor blocks and the metadata blocks are written to the log;
+//! 3. The commit block is written to the log, after which the transaction is committed;
+//! 4. The metadata blocks are written to their locations, i.e., checkpointed;
+//! 5. The journal superblock is updated to mark the log as empty.
+//!
+//! If a crash happens before step 3, none of the metadata is written to their locations. If a
+//! crash happens after step 3, the transaction is replayed at the next mount. So the metadata
+//! on the device are always consistent.
+//!
+//! Since the log is checkpointed right after each commit, there is at most one transaction in
+//! the log, and no revoke records are needed. However, the revoke records written by Linux are
+//! respected when recovering.
+//!
+//! # Limitation
+//!
+//! 1. The checksums, the 64-bit block numbers, and the other features of JBD2 that need
+//!    additional fields in the log are not supported.
+//! 2. The metadata updates are not grouped into atomic operations. If the running transaction
+//!    is full, it is committed early, and the metadata updates that are being written back at
+//!    the same time may be split into two transactions.
+//!
+//! Ref: <https://www.kernel.org/doc/html/latest/filesystems/ext4/journal.html>
+
+use ostd::const_assert;
+
+use super::{block_ptr::Ext2Bid, fs::Ext2, prelude::*};
+
+/// The magic number of the blocks in the journal.
+const MAGIC_NUM: u32 = 0xc03b3998;
+
+const HEADER_SIZE: usize = core::mem::size_of::<RawHeader>();
+
+/// The size of a block tag in a descriptor block, without the 64-bit block numbers and the
+/// checksums.
+const TAG_SIZE: usize = 8;
+
+const UUID_SIZE: usize = 16;
+
+/// The maximum number of the block tags in a descriptor block.
+///
+/// The first tag is followed by the UUID of the journal, while the others are not.
+const TAGS_PER_DESCRIPTOR: usize = (BLOCK_SIZE - HEADER_SIZE - UUID_SIZE) / TAG_SIZE;
+
+/// The offset of the first revoke record in a revoke block.
+const REVOKE_RECORDS_OFFSET: usize = HEADER_SIZE + 4;
+
+/// The contents of a block.
+type Block = Box<[u8]>;
+
+/// The journal of the metadata.
+pub(super) struct Journal {
+    block_device: Arc<dyn BlockDevice>,
+    /// The device block IDs of the blocks in the journal.
+    bids: Vec<Ext2Bid>,
+    /// The first block of the log in the journal.
+    first: u32,
+    /// The number of the blocks in the journal.
+    max_len: u32,
+    /// The maximum number of the metadata blocks in a transaction.
+    max_transaction_blocks: usize,
+    uuid: [u8; UUID_SIZE],
+    /// The journal superblock, which is locked while committing a transaction.
+    super_block: Mutex<RawSuperBlock>,
+    transactions: Mutex<Transactions>,
+}
+
+#[derive(Default)]
+struct Transactions {
+    /// The metadata blocks written since the last commit.
+    running: BTreeMap<Ext2Bid, Block>,
+    /// The metadata blocks that are being committed and are not checkpointed yet.
+    committing: Arc<BTreeMap<Ext2Bid, Block>>,
+}
+
+impl Journal {
+    /// Loads the journal from the inode of `ino` in the `fs`.
+    pub(super) fn load(fs: &Ext2, ino: u32, block_device: Arc<dyn BlockDevice>) -> Result<Self> {
+        let inode = fs.lookup_inode(ino)?;
+        if inode.inode_type() != InodeType::File {
+            return_errno_with_message!(Errno::EINVAL, "the journal is not a regular file");
+        }
+        let nblocks = (inode.file_size() / BLOCK_SIZE) as Ext2Bid;
+        if nblocks == 0 {
+            return_errno_with_message!(Errno::EINVAL, "the journal is empty");
+        }
+        let mut bids = Vec::with_capacity(nblocks as usize);
+        for device_range in inode.device_ranges(0..nblocks)? {
+            if device_range.start == 0 {
+                return_errno_with_message!(Errno::EINVAL, "the journal has holes");
+            }
+            bids.extend(device_range);
+        }
+
+        let super_block = block_device.read_val::<RawSuperBlock>(bids[0] as usize * BLOCK_SIZE)?;
+        if super_block.header.magic.get() != MAGIC_NUM {
+            return_errno_with_message!(Errno::EINVAL, "bad journal magic number");
+        }
+        match BlockType::try_from(super_block.header.block_type.get()) {
+            Ok(BlockType::SuperBlockV1) => (),
+            Ok(BlockType::SuperBlockV2) => {
+                if super_block.feature_compat.get() != 0
+                    || super_block.feature_ro_compat.get() != 0
+                    || (super_block.feature_incompat.get() & !FEATURE_INCOMPAT_REVOKE) != 0
+                {
+                    return_errno_with_message!(Errno::EINVAL, "not supported journal features");
+                }
+            }
+            _ => return_errno_with_message!(Errno::EINVAL, "invalid journal superblock"),
+        }
+        if super_block.block_size.get() as usize != BLOCK_SIZE {
+            return_errno_with_message!(Errno::EINVAL, "not supported journal block size");
+        }
+        let first = super_block.first.get();
+        let max_len = super_block.max_len.get();
+        if max_len as usize > bids.len() || first == 0 || first >= max_len {
+            return_errno_with_message!(Errno::EINVAL, "invalid journal size");
+        }
+
+        // A transaction of `n` metadata blocks takes `n.div_ceil(TAGS_PER_DESCRIPTOR)`
+        // descriptor blocks and a commit block in the log.
+        let log_len = (max_len - first) as usize;
+        let max_transaction_blocks =
+            (log_len - 1) * TAGS_PER_DESCRIPTOR / (TAGS_PER_DESCRIPTOR + 1);
+        if max_transaction_blocks == 0 {
+            return_errno_with_message!(Errno::EINVAL, "the journal is too small");
+        }
+
+        Ok(Self {
+            block_device,
+            bids,
+            first,
+            max_len,
+            max_transaction_blocks,
+            uuid: super_block.uuid,
+            super_block: Mutex::new(super_block),
+            transactions: Mutex::new(Transactions::default()),
+        })
+    }
+
+    /// Recovers the metadata by replaying the committed transactions in the log.
+    ///
+    /// The metadata that are already loaded may be out of date after the recovery.
+    pub(super) fn recover(&self) -> Result<()> {
+        let mut super_block = self.super_block.lock();
+        let start = super_block.start.get();
+        if start == 0 {
+            return Ok(());
+        }
+        if start < self.first || start >= self.max_len {
+            return_errno_with_message!(Errno::EINVAL, "invalid start of the journal log");
+        }
+
+        // Finds the committed transactions. The log ends at the first block that does not
+        // belong to the next transaction, or at the transaction that is not committed.
+        let mut transactions = Vec::new();
+        let mut transaction = ScannedTransaction::default();
+        let mut sequence = super_block.sequence.get();
+        let mut pos = start;
+        for _ in 0..self.max_len {
+            let block = self.read_log_block(pos)?;
+            let header = RawHeader::from_bytes(&block[..HEADER_SIZE]);
+            if header.magic.get() != MAGIC_NUM || header.sequence.get() != sequence {
+                break;
+            }
+            match BlockType::try_from(header.block_type.get()) {
+                Ok(BlockType::Descriptor) => {
+                    for (bid, flags) in parse_tags(&block) {
+                        pos = self.next_pos(pos);
+                        let is_escaped = flags.contains(TagFlags::ESCAPE);
+                        transaction.blocks.push((bid, pos, is_escaped));
+                    }
+                }
+                Ok(BlockType::Revoke) => transaction.revoked.extend(parse_revoke_records(&block)),
+                Ok(BlockType::Commit) => {
+                    transactions.push((sequence, core::mem::take(&mut transaction)));
+                    sequence = sequence.wrapping_add(1);
+                }
+                _ => break,
+            }
+            pos = self.next_pos(pos);
+        }
+
+        // Replays the blocks in order, except those revoked by the same or later transactions.
+        let mut revoked = BTreeMap::new();
+        for (sequence, transaction) in transactions.iter() {
+            for bid in transaction.revoked.iter() {
+                revoked.insert(*bid, *sequence);
+            }
+        }
+        for (sequence, transaction) in transactions.iter() {
+            for &(bid, pos, is_escaped) in transaction.blocks.iter() {
+                if revoked
+                    .get(&bid)
+                    .is_some_and(|revoked_sequence| !is_before(*revoked_sequence, *sequence))
+                {
+                    continue;
+                }
+                let mut block = self.read_log_block(pos)?;
+                if is_escaped {
+                    block[..4].copy_from_slice(&MAGIC_NUM.to_be_bytes());
+                }
+                wait(self.write_block(bid, &block)?)?;
+            }
+        }
+        self.flush()?;
+
+        // Empties the log. Like Linux, the sequence of the transaction that is not committed is
+        // skipped, so its blocks that remain in the log are never taken as a later one.
+        super_block.start = Be32::new(0);
+        super_block.sequence = Be32::new(sequence.wrapping_add(1));
+        wait(self.write_super_block(&super_block)?)?;
+        self.flush()
+    }
+
+    /// Writes contiguous metadata blocks starting from the `bid` to the running transaction.
+    pub(super) fn write_blocks(&self, bid: Ext2Bid, bio_segment: &BioSegment) -> Result<()> {
+        for idx in 0..bio_segment.nblocks() {
+            let mut block = new_block();
+            bio_segment.read_bytes(idx * BLOCK_SIZE, &mut block)?;
+            self.add_block(bid + idx as Ext2Bid, block)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the metadata bytes at the `offset` of the block device to the running
+    /// transaction.
+    ///
+    /// The rest of the blocks that contain the bytes are kept unchanged.
+    pub(super) fn write_bytes(&self, mut offset: usize, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            let bid = (offset / BLOCK_SIZE) as Ext2Bid;
+            let offset_in_block = offset % BLOCK_SIZE;
+            let len = buf.len().min(BLOCK_SIZE - offset_in_block);
+
+            let mut block = match self.find_block(bid) {
+                Some(block) => block,
+                None => {
+                    let mut block = new_block();
+                    self.block_device
+                        .read_bytes(bid as usize * BLOCK_SIZE, &mut block)?;
+                    block
+                }
+            };
+            block[offset_in_block..offset_in_block + len].copy_from_slice(&buf[..len]);
+            self.add_block(bid, block)?;
+
+            offset += len;
+            buf = &buf[len..];
+        }
+        Ok(())
+    }
+
+    /// Returns whether some of the blocks in `range` are in the journal but not written to
+    /// their locations yet.
+    pub(super) fn contains_blocks(&self, range: Range<Ext2Bid>) -> bool {
+        let transactions = self.transactions.lock();
+        transactions.running.range(range.clone()).next().is_some()
+            || transactions.committing.range(range).next().is_some()
+    }
+
+    /// Returns the latest contents of the blocks in `range` that are in the journal but not
+    /// written to their locations yet.
+    pub(super) fn find_blocks(&self, range: Range<Ext2Bid>) -> Vec<(Ext2Bid, Block)> {
+        let transactions = self.transactions.lock();
+        let mut blocks = BTreeMap::new();
+        for (bid, block) in transactions.committing.range(range.clone()) {
+            blocks.insert(*bid, block.clone());
+        }
+        for (bid, block) in transactions.running.range(range) {
+            blocks.insert(*bid, block.clone());
+        }
+        blocks.into_iter().collect()
+    }
+
+    /// Removes the freed blocks from the running transaction.
+    pub(super) fn forget_blocks(&self, range: Range<Ext2Bid>) {
+        self.transactions
+            .lock()
+            .running
+            .retain(|bid, _| !range.contains(bid));
+    }
+
+    /// Commits the running transaction and checkpoints it.
+    ///
+    /// The data blocks that are written before must have been completed.
+    pub(super) fn commit(&self) -> Result<()> {
+        let mut super_block = self.super_block.lock();
+        let blocks = {
+            let mut transactions = self.transactions.lock();
+            if transactions.running.is_empty() {
+                return Ok(());
+            }
+            let blocks = Arc::new(core::mem::take(&mut transactions.running));
+            transactions.committing = blocks.clone();
+            blocks
+        };
+        let sequence = super_block.sequence.get();
+
+        // Writes the descriptor blocks and the metadata blocks to the log.
+        super_block.start = Be32::new(self.first);
+        let mut bio_waiter = self.write_super_block(&super_block)?;
+        let mut pos = self.first;
+        let blocks_vec: Vec<_> = blocks.iter().collect();
+        for chunk in blocks_vec.chunks(TAGS_PER_DESCRIPTOR) {
+            let mut descriptor = new_block();
+            let header = RawHeader::new(BlockType::Descriptor, sequence);
+            descriptor[..HEADER_SIZE].copy_from_slice(header.as_bytes());
+            let mut offset = HEADER_SIZE;
+            for (idx, (bid, block)) in chunk.iter().enumerate() {
+                let mut flags = TagFlags::empty();
+                if idx > 0 {
+                    flags |= TagFlags::SAME_UUID;
+                }
+                if idx == chunk.len() - 1 {
+                    flags |= TagFlags::LAST_TAG;
+                }
+                if needs_escape(block) {
+                    flags |= TagFlags::ESCAPE;
+                }
+                descriptor[offset..offset + 4].copy_from_slice(&bid.to_be_bytes());
+                descriptor[offset + 6..offset + 8].copy_from_slice(&flags.bits().to_be_bytes());
+                offset += TAG_SIZE;
+                if idx == 0 {
+                    descriptor[offset..offset + UUID_SIZE].copy_from_slice(&self.uuid);
+                    offset += UUID_SIZE;
+                }
+            }
+            bio_waiter.concat(self.write_log_block(pos, &descriptor)?);
+            pos += 1;
+
+            for (_, block) in chunk.iter() {
+                let waiter = if needs_escape(block) {
+                    let mut escaped_block = (*block).clone();
+                    escaped_block[..4].fill(0);
+                    self.write_log_block(pos, &escaped_block)?
+                } else {
+                    self.write_log_block(pos, block)?
+                };
+                bio_waiter.concat(waiter);
+                pos += 1;
+            }
+        }
+        wait(bio_waiter)?;
+        self.flush()?;
+
+        // Writes the commit block.
+        let mut commit_block = new_block();
+        let header = RawHeader::new(BlockType::Commit, sequence);
+        commit_block[..HEADER_SIZE].copy_from_slice(header.as_bytes());
+        wait(self.write_log_block(pos, &commit_block)?)?;
+        self.flush()?;
+
+        // Checkpoints the metadata blocks.
+        let mut bio_waiter = BioWaiter::new();
+        for (bid, block) in blocks.iter() {
+            bio_waiter.concat(self.write_block(*bid, block)?);
+        }
+        wait(bio_waiter)?;
+        self.flush()?;
+
+        // Empties the log.
+        super_block.start = Be32::new(0);
+        super_block.sequence = Be32::new(sequence.wrapping_add(1));
+        wait(self.write_super_block(&super_block)?)?;
+
+        self.transactions.lock().committing = Arc::default();
+        Ok(())
+    }
+
+    /// Adds the metadata block to the running transaction.
+    ///
+    /// If the running transaction is full, it is committed first.
+    fn add_block(&self, bid: Ext2Bid, block: Block) -> Result<()> {
+        loop {
+            let mut transactions = self.transactions.lock();
+            if transactions.running.len() < self.max_transaction_blocks
+                || transactions.running.contains_key(&bid)
+            {
+                transactions.running.insert(bid, block);
+                return Ok(());
+            }
+            drop(transactions);
+            self.commit()?;
+        }
+    }
+
+    /// Returns the latest content of the block if it is in the journal but not written to its
+    /// location yet.
+    fn find_block(&self, bid: Ext2Bid) -> Option<Block> {
+        let transactions = self.transactions.lock();
+        transactions
+            .running
+            .get(&bid)
+            .or_else(|| transactions.committing.get(&bid))
+            .cloned()
+    }
+
+    /// Returns the position of the block after the one at `pos` in the log, which wraps around
+    /// at the end of the journal.
+    fn next_pos(&self, pos: u32) -> u32 {
+        if pos + 1 >= self.max_len {
+            self.first
+        } else {
+            pos + 1
+        }
+    }
+
+    fn read_log_block(&self, pos: u32) -> Result<Block> {
+        let mut block = new_block();
+        self.block_device
+            .read_bytes(self.bids[pos as usize] as usize * BLOCK_SIZE, &mut block)?;
+        Ok(block)
+    }
+
+    fn write_log_block(&self, pos: u32, block: &[u8]) -> Result<BioWaiter> {
+        self.write_block(self.bids[pos as usize], block)
+    }
+
+    fn write_block(&self, bid: Ext2Bid, block: &[u8]) -> Result<BioWaiter> {
+        let bio_segment = BioSegment::alloc(1, BioDirection::ToDevice);
+        bio_segment.write_bytes(0, block)?;
+        let waiter = self
+            .block_device
+            .write_blocks_async(Bid::new(bid as u64), bio_segment)?;
+        Ok(waiter)
+    }
+
+    fn write_super_block(&self, super_block: &RawSuperBlock) -> Result<BioWaiter> {
+        let waiter = self
+            .block_device
+            .write_bytes_async(self.bids[0] as usize * BLOCK_SIZE, super_block.as_bytes())?;
+        Ok(waiter)
+    }
+
+    /// Flushes the volatile write cache of the block device, so that the completed writes
+    /// reach the persistent storage.
+    fn flush(&self) -> Result<()> {
+        match self.block_device.sync()? {
+            BioStatus::Complete => Ok(()),
+            err_status => Err(Error::from(err_status)),
+        }
+    }
+}
+
+impl Debug for Journal {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("Journal")
+            .field("first", &self.first)
+            .field("max_len", &self.max_len)
+            .field("max_transaction_blocks", &self.max_transaction_blocks)
+            .finish()
+    }
+}
+
+/// A committed transaction found in the log when recovering.
+#[derive(Default)]
+struct ScannedTransaction {
+    /// The metadata blocks, each of which is the device block ID, the position in the log,
+    /// and whether it is escaped.
+    blocks: Vec<(Ext2Bid, u32, bool)>,
+    /// The revoked device block IDs.
+    revoked: Vec<Ext2Bid>,
+}
+
+fn new_block() -> Block {
+    vec![0u8; BLOCK_SIZE].into_boxed_slice()
+}
+
+/// Returns whether the block starts with the magic number, which must be escaped in the log.
+fn needs_escape(block: &[u8]) -> bool {
+    block[..4] == MAGIC_NUM.to_be_bytes()
+}
+
+/// Returns whether the transaction of `sequence` is before that of `other`.
+///
+/// The sequences wrap around, so they are compared like the TCP sequence numbers.
+fn is_before(sequence: u32, other: u32) -> bool {
+    (sequence.wrapping_sub(other) as i32) < 0
+}
+
+/// Parses the block tags in the descriptor block.
+fn parse_tags(descriptor: &[u8]) -> Vec<(Ext2Bid, TagFlags)> {
+    let mut tags = Vec::new();
+    let mut offset = HEADER_SIZE;
+    while offset + TAG_SIZE <= BLOCK_SIZE {
+        let bid = u32::from_be_bytes(descriptor[offset..offset + 4].try_into().unwrap());
+        let flags = TagFlags::from_bits_truncate(u16::from_be_bytes(
+            descriptor[offset + 6..offset + 8].try_into().unwrap(),
+        ));
+        tags.push((bid, flags));
+
+        offset += TAG_SIZE;
+        if !flags.contains(TagFlags::SAME_UUID) {
+            offset += UUID_SIZE;
+        }
+        if flags.contains(TagFlags::LAST_TAG) {
+            break;
+        }
+    }
+    tags
+}
+
+/// Parses the revoke records in the revoke block.
+fn parse_revoke_records(revoke_block: &[u8]) -> Vec<Ext2Bid> {
+    // The number of the bytes used in the block, including the header.
+    let count = u32::from_be_bytes(
+        revoke_block[HEADER_SIZE..REVOKE_RECORDS_OFFSET]
+            .try_into()
+            .unwrap(),
+    ) as usize;
+    let end = count.clamp(REVOKE_RECORDS_OFFSET, BLOCK_SIZE);
+    revoke_block[REVOKE_RECORDS_OFFSET..end]
+        .chunks_exact(4)
+        .map(|record| u32::from_be_bytes(record.try_into().unwrap()))
+        .collect()
+}
+
+/// Waits for the completion of the writes.
+fn wait(bio_waiter: BioWaiter) -> Result<()> {
+    match bio_waiter.wait() {
+        Some(BioStatus::Complete) => Ok(()),
+        _ => return_errno_with_message!(Errno::EIO, "failed to write the journal"),
+    }
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, TryFromInt)]
+enum BlockType {
+    Descriptor = 1,
+    Commit = 2,
+    SuperBlockV1 = 3,
+    SuperBlockV2 = 4,
+    Revoke = 5,
+}
+
+/// The journal has revoke records.
+const FEATURE_INCOMPAT_REVOKE: u32 = 1 << 0;
+
+bitflags! {
+    /// The flags of a block tag.
+    struct TagFlags: u16 {
+        /// The block starts with the magic number, which is replaced with zeros in the log.
+        const ESCAPE = 1 << 0;
+        /// The tag is not followed by the UUID, which is the same as the previous one.
+        const SAME_UUID = 1 << 1;
+        /// The block is deleted by this transaction.
+        const DELETED = 1 << 2;
+        /// This is the last tag in the descriptor block.
+        const LAST_TAG = 1 << 3;
+    }
+}
+
+/// A big-endian 32-bit integer, in which all the fields of the journal are stored.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct Be32(u32);
+
+impl Be32 {
+    fn new(value: u32) -> Self {
+        Self(value.to_be())
+    }
+
+    fn get(self) -> u32 {
+        u32::from_be(self.0)
+    }
+}
+
+/// The header of the blocks in the journal, except the data blocks.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawHeader {
+    magic: Be32,
+    block_type: Be32,
+    sequence: Be32,
+}
+
+impl RawHeader {
+    fn new(block_type: BlockType, sequence: u32) -> Self {
+        Self {
+            magic: Be32::new(MAGIC_NUM),
+            block_type: Be32::new(block_type as u32),
+            sequence: Be32::new(sequence),
+        }
+    }
+}
+
+const_assert!(core::mem::size_of::<RawSuperBlock>() == 1024);
+
+/// The raw journal superblock, which is at the first block of the journal.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawSuperBlock {
+    header: RawHeader,
+    block_size: Be32,
+    /// The number of the blocks in the journal.
+    max_len: Be32,
+    /// The first block of the log.
+    first: Be32,
+    /// The sequence of the first transaction in the log.
+    sequence: Be32,
+    /// The block of the first transaction in the log, or zero if the log is empty.
+    start: Be32,
+    errno: Be32,
+    //
+    // These fields are valid for `BlockType::SuperBlockV2` only.
+    //
+    feature_compat: Be32,
+    feature_incompat: Be32,
+    feature_ro_compat: Be32,
+    uuid: [u8; UUID_SIZE],
+    reserved: [u8; 960],
+}
@@ -14,6 +14,9 @@
 //!    stored in PageCache, which accelerates the performance of data access.
 //! 3. Compatible with queue-based block device. The filesystem can submits multiple
 //!    BIO requests to be block device at once, thereby enhancing I/O performance.
+//! 4. Crash-consistent metadata. If the filesystem has a journal, i.e., it is created by
+//!    `mke2fs -O has_journal`, the metadata are updated through the journal in the ordered
+//!    mode of Ext3, and the committed updates are replayed at the next mount after a crash.
 //!
 //! # Example
 //!
@@ -47,6 +50,7 @@ mod fs;
 mod impl_for_vfs;
 mod indirect_block_cache;
 mod inode;
+mod journal;
 mod prelude;
 mod super_block;
 mod utils;
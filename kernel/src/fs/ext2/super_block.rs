@@ -93,6 +93,15 @@ pub struct SuperBlock {
     prealloc_file_blocks: u8,
     /// Number of blocks to preallocate for directories.
     prealloc_dir_blocks: u8,
+    ///
+    /// This fields are valid if the FeatureCompatSet::HAS_JOURNAL is set.
+    ///
+    /// Uuid of journal superblock.
+    journal_uuid: [u8; 16],
+    /// Inode number of journal file.
+    journal_ino: u32,
+    /// Device number of journal file.
+    journal_dev: u32,
 }
 
 impl TryFrom<RawSuperBlock> for SuperBlock {
@@ -174,6 +183,9 @@ impl TryFrom<RawSuperBlock> for SuperBlock {
             last_mounted_dir: sb.last_mounted_dir,
             prealloc_file_blocks: sb.prealloc_file_blocks,
             prealloc_dir_blocks: sb.prealloc_dir_blocks,
+            journal_uuid: sb.journal_uuid,
+            journal_ino: sb.journal_ino,
+            journal_dev: sb.journal_dev,
         })
     }
 }
@@ -244,6 +256,27 @@ impl SuperBlock {
         self.feature_ro_compat
     }
 
+    /// Returns the inode number of the journal.
+    ///
+    /// Returns `None` if there is no journal, or the journal is on an external device.
+    pub(super) fn journal_ino(&self) -> Option<u32> {
+        if self.feature_compat.contains(FeatureCompatSet::HAS_JOURNAL) && self.journal_ino != 0 {
+            Some(self.journal_ino)
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether the journal needs to be replayed before the filesystem is used.
+    pub(super) fn needs_recovery(&self) -> bool {
+        self.feature_incompat.contains(FeatureInCompatSet::RECOVER)
+    }
+
+    /// Marks that the journal needs to be replayed, i.e., the filesystem is in use.
+    pub(super) fn set_needs_recovery(&mut self) {
+        self.feature_incompat |= FeatureInCompatSet::RECOVER;
+    }
+
     /// Returns the number of free blocks.
     pub fn free_blocks_count(&self) -> u32 {
         self.free_blocks_count
@@ -528,6 +561,9 @@ impl From<&SuperBlock> for RawSuperBlock {
             last_mounted_dir: sb.last_mounted_dir,
             prealloc_file_blocks: sb.prealloc_file_blocks,
             prealloc_dir_blocks: sb.prealloc_dir_blocks,
+            journal_uuid: sb.journal_uuid,
+            journal_ino: sb.journal_ino,
+            journal_dev: sb.journal_dev,
             ..Default::default()
         }
     }
@@ -6,10 +6,14 @@
 
 use super::inode_handle::InodeHandle;
 use crate::{
-    fs::utils::{AccessMode, FallocMode, InodeMode, IoctlCmd, Metadata, SeekFrom, StatusFlags},
+    fs::utils::{
+        AccessMode, FallocMode, FileAdvice, FileSeals, InodeMode, IoctlCmd, Metadata, SeekFrom,
+        StatusFlags,
+    },
     net::socket::Socket,
     prelude::*,
     process::{signal::Pollable, Gid, Uid},
+    vm::vmo::Vmo,
 };
 
 /// The basic operations defined on a file
@@ -86,6 +90,16 @@ pub trait FileLike: Pollable + Send + Sync + Any {
         return_errno_with_message!(Errno::EINVAL, "set_status_flags is not supported");
     }
 
+    /// Replaces the status flags in `mask` with those in `new_flags`.
+    ///
+    /// The status flags outside `mask` are kept, even if they are changed concurrently. The
+    /// default implementation is a read-modify-write, so files that support more than one status
+    /// flag should override it to update the flags atomically.
+    fn update_status_flags(&self, mask: StatusFlags, new_flags: StatusFlags) -> Result<()> {
+        let status_flags = (self.status_flags() - mask) | (new_flags & mask);
+        self.set_status_flags(status_flags)
+    }
+
     fn access_mode(&self) -> AccessMode {
         AccessMode::O_RDWR
     }
@@ -98,9 +112,51 @@ pub trait FileLike: Pollable + Send + Sync + Any {
         return_errno_with_message!(Errno::EOPNOTSUPP, "fallocate is not supported");
     }
 
+    /// Advises the access pattern of the data within the range of the file.
+    ///
+    /// A `len` of zero means that the range extends to the end of the file.
+    fn fadvise(&self, advice: FileAdvice, offset: usize, len: usize) -> Result<()> {
+        return_errno_with_message!(Errno::ESPIPE, "fadvise is not supported");
+    }
+
+    /// Returns the capacity of the pipe if the file is an end of a pipe.
+    fn pipe_capacity(&self) -> Result<usize> {
+        return_errno_with_message!(Errno::EBADF, "the file is not a pipe");
+    }
+
+    /// Sets the capacity of the pipe if the file is an end of a pipe.
+    ///
+    /// The capacity must be a power of two. See [`set_pipe_capacity`] for how it is derived from
+    /// the requested capacity.
+    ///
+    /// [`set_pipe_capacity`]: crate::fs::pipe::set_pipe_capacity
+    fn set_pipe_capacity(&self, capacity: usize) -> Result<()> {
+        return_errno_with_message!(Errno::EBADF, "the file is not a pipe");
+    }
+
+    /// Adds the seals to the file.
+    ///
+    /// Only files backed by shared memory can be sealed, so other files fail with `EINVAL`.
+    fn add_seals(&self, seals: FileSeals) -> Result<()> {
+        return_errno_with_message!(Errno::EINVAL, "the file does not support seals");
+    }
+
+    /// Returns the seals of the file.
+    fn seals(&self) -> Result<FileSeals> {
+        return_errno_with_message!(Errno::EINVAL, "the file does not support seals");
+    }
+
     fn as_socket(&self) -> Option<&dyn Socket> {
         None
     }
+
+    /// Returns the VMO that backs the memory mappings of the file.
+    ///
+    /// This is used to map the files that are not related to inodes, e.g., io_uring instances.
+    /// The second return value is the offset in the VMO that corresponds to `offset` in the file.
+    fn mmap_vmo(&self, offset: usize, len: usize) -> Result<(Vmo, usize)> {
+        return_errno_with_message!(Errno::ENODEV, "mmap is not supported");
+    }
 }
 
 impl dyn FileLike {
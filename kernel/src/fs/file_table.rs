@@ -1,42 +1,57 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::sync::atomic::{AtomicU8, Ordering};
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicU8, Ordering},
+};
 
 use aster_util::slot_vec::SlotVec;
+use ostd::{
+    sync::{Rcu, RcuOption},
+    task::disable_preempt,
+};
 
 use super::{
     file_handle::FileLike,
     fs_resolver::{FsPath, FsResolver, AT_FDCWD},
-    utils::{AccessMode, InodeMode},
+    utils::{AccessMode, CreationFlags, InodeMode},
 };
 use crate::{
     events::{Events, IoEvents, Observer, Subject},
     fs::utils::StatusFlags,
     prelude::*,
     process::{
-        posix_thread::FileTableRefMut,
-        signal::{constants::SIGIO, signals::kernel::KernelSignal, PollAdaptor},
-        Pid, Process,
+        posix_thread::{AsPosixThread, FileTableRefMut},
+        signal::{
+            constants::{SIGIO, SIGURG},
+            signals::kernel::KernelSignal,
+            PollAdaptor,
+        },
+        Pgid, Pid, Process, ProcessGroup, ResourceType,
     },
+    thread::{Thread, Tid},
 };
 
 pub type FileDesc = i32;
 
 pub struct FileTable {
     table: SlotVec<FileTableEntry>,
+    fd_bitmap: FdBitmap,
+    lookup: Arc<FileLookup>,
     subject: Subject<FdEvents>,
 }
 
 impl FileTable {
-    pub const fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             table: SlotVec::new(),
+            fd_bitmap: FdBitmap::new(),
+            lookup: Arc::new(FileLookup::new()),
             subject: Subject::new(),
         }
     }
 
     pub fn new_with_stdio() -> Self {
-        let mut table = SlotVec::new();
         let fs_resolver = FsResolver::new();
         let tty_path = FsPath::new(AT_FDCWD, "/dev/console").expect("cannot find tty");
         let stdin = {
@@ -54,13 +69,13 @@ impl FileTable {
             let mode = InodeMode::S_IWUSR;
             fs_resolver.open(&tty_path, flags, mode.bits()).unwrap()
         };
-        table.put(FileTableEntry::new(Arc::new(stdin), FdFlags::empty()));
-        table.put(FileTableEntry::new(Arc::new(stdout), FdFlags::empty()));
-        table.put(FileTableEntry::new(Arc::new(stderr), FdFlags::empty()));
-        Self {
-            table,
-            subject: Subject::new(),
+
+        let mut file_table = Self::new();
+        for file in [stdin, stdout, stderr] {
+            let fd = file_table.fd_bitmap.first_free_from(0);
+            file_table.put_entry(fd, FileTableEntry::new(Arc::new(file), FdFlags::empty()));
         }
+        file_table
     }
 
     pub fn len(&self) -> usize {
@@ -71,37 +86,40 @@ impl FileTable {
         self.table.is_empty()
     }
 
-    pub fn dup(&mut self, fd: FileDesc, new_fd: FileDesc, flags: FdFlags) -> Result<FileDesc> {
-        let file = self
-            .table
-            .get(fd as usize)
-            .map(|entry| entry.file.clone())
-            .ok_or(Error::with_message(Errno::ENOENT, "No such file"))?;
-
-        // Get the lowest-numbered available fd equal to or greater than `new_fd`.
-        let get_min_free_fd = || -> usize {
-            let new_fd = new_fd as usize;
-            if self.table.get(new_fd).is_none() {
-                return new_fd;
-            }
+    /// Returns the lock-free view of the files in the file table.
+    pub fn lookup(&self) -> &Arc<FileLookup> {
+        &self.lookup
+    }
 
-            for idx in new_fd + 1..self.len() {
-                if self.table.get(idx).is_none() {
-                    return idx;
-                }
-            }
-            self.len()
-        };
+    /// Duplicates the file descriptor `fd` to the lowest-numbered available file descriptor
+    /// equal to or greater than `new_fd`.
+    ///
+    /// # Errors
+    ///
+    /// This method will fail with [`EMFILE`] if no file descriptors are available within the
+    /// `RLIMIT_NOFILE` resource limit.
+    ///
+    /// [`EMFILE`]: crate::error::Errno::EMFILE
+    pub fn dup(&mut self, fd: FileDesc, new_fd: FileDesc, flags: FdFlags) -> Result<FileDesc> {
+        let file = self.get_file(fd)?.clone();
 
-        let min_free_fd = get_min_free_fd();
-        let entry = FileTableEntry::new(file, flags);
-        self.table.put_at(min_free_fd, entry);
+        let min_free_fd = self.alloc_fd(new_fd as usize)?;
+        self.put_entry(min_free_fd, FileTableEntry::new(file, flags));
         Ok(min_free_fd as FileDesc)
     }
 
-    pub fn insert(&mut self, item: Arc<dyn FileLike>, flags: FdFlags) -> FileDesc {
-        let entry = FileTableEntry::new(item, flags);
-        self.table.put(entry) as FileDesc
+    /// Inserts the file to the lowest-numbered available file descriptor.
+    ///
+    /// # Errors
+    ///
+    /// This method will fail with [`EMFILE`] if no file descriptors are available within the
+    /// `RLIMIT_NOFILE` resource limit.
+    ///
+    /// [`EMFILE`]: crate::error::Errno::EMFILE
+    pub fn insert(&mut self, item: Arc<dyn FileLike>, flags: FdFlags) -> Result<FileDesc> {
+        let fd = self.alloc_fd(0)?;
+        self.put_entry(fd, FileTableEntry::new(item, flags));
+        Ok(fd as FileDesc)
     }
 
     pub fn insert_at(
@@ -111,7 +129,7 @@ impl FileTable {
         flags: FdFlags,
     ) -> Option<Arc<dyn FileLike>> {
         let entry = FileTableEntry::new(item, flags);
-        let entry = self.table.put_at(fd as usize, entry);
+        let entry = self.put_entry(fd as usize, entry);
         if entry.is_some() {
             let events = FdEvents::Close(fd);
             self.notify_fd_events(&events);
@@ -121,7 +139,7 @@ impl FileTable {
     }
 
     pub fn close_file(&mut self, fd: FileDesc) -> Option<Arc<dyn FileLike>> {
-        let removed_entry = self.table.remove(fd as usize)?;
+        let removed_entry = self.remove_entry(fd as usize)?;
 
         let events = FdEvents::Close(fd);
         self.notify_fd_events(&events);
@@ -131,28 +149,38 @@ impl FileTable {
     }
 
     pub fn close_files_on_exec(&mut self) -> Vec<Arc<dyn FileLike>> {
-        self.close_files(|entry| entry.flags().contains(FdFlags::CLOEXEC))
+        self.close_files(0..usize::MAX, |entry| {
+            entry.flags().contains(FdFlags::CLOEXEC)
+        })
+    }
+
+    /// Closes all the files whose file descriptors are in the range.
+    pub fn close_files_in_range(&mut self, range: Range<usize>) -> Vec<Arc<dyn FileLike>> {
+        self.close_files(range, |_| true)
     }
 
-    fn close_files<F>(&mut self, should_close: F) -> Vec<Arc<dyn FileLike>>
+    /// Sets the close-on-exec flag of all the file descriptors in the range.
+    pub fn set_cloexec_in_range(&self, range: Range<usize>) {
+        for fd in self.fd_bitmap.allocated_fds(range) {
+            let entry = self.table.get(fd).unwrap();
+            entry.set_flags(entry.flags() | FdFlags::CLOEXEC);
+        }
+    }
+
+    fn close_files<F>(&mut self, range: Range<usize>, should_close: F) -> Vec<Arc<dyn FileLike>>
     where
         F: Fn(&FileTableEntry) -> bool,
     {
         let mut closed_files = Vec::new();
         let closed_fds: Vec<FileDesc> = self
-            .table
-            .idxes_and_items()
-            .filter_map(|(idx, entry)| {
-                if should_close(entry) {
-                    Some(idx as FileDesc)
-                } else {
-                    None
-                }
-            })
+            .fd_bitmap
+            .allocated_fds(range)
+            .filter(|fd| should_close(self.table.get(*fd).unwrap()))
+            .map(|fd| fd as FileDesc)
             .collect();
 
         for fd in closed_fds {
-            let removed_entry = self.table.remove(fd as usize).unwrap();
+            let removed_entry = self.remove_entry(fd as usize).unwrap();
             let events = FdEvents::Close(fd);
             self.notify_fd_events(&events);
             removed_entry.notify_fd_events(&events);
@@ -163,21 +191,20 @@ impl FileTable {
     }
 
     pub fn get_file(&self, fd: FileDesc) -> Result<&Arc<dyn FileLike>> {
-        self.table
-            .get(fd as usize)
-            .map(|entry| &entry.file)
-            .ok_or(Error::with_message(Errno::EBADF, "fd not exits"))
+        self.get_entry(fd).map(|entry| &entry.file)
     }
 
     pub fn get_entry(&self, fd: FileDesc) -> Result<&FileTableEntry> {
-        self.table
-            .get(fd as usize)
+        usize::try_from(fd)
+            .ok()
+            .and_then(|fd| self.table.get(fd))
             .ok_or(Error::with_message(Errno::EBADF, "fd not exits"))
     }
 
     pub fn get_entry_mut(&mut self, fd: FileDesc) -> Result<&mut FileTableEntry> {
-        self.table
-            .get_mut(fd as usize)
+        usize::try_from(fd)
+            .ok()
+            .and_then(|fd| self.table.get_mut(fd))
             .ok_or(Error::with_message(Errno::EBADF, "fd not exits"))
     }
 
@@ -198,6 +225,31 @@ impl FileTable {
     fn notify_fd_events(&self, events: &FdEvents) {
         self.subject.notify_observers(events);
     }
+
+    /// Allocates the lowest-numbered available file descriptor equal to or greater than `start`.
+    fn alloc_fd(&self, start: usize) -> Result<usize> {
+        let fd = self.fd_bitmap.first_free_from(start);
+        if fd >= nofile_limit() {
+            return_errno_with_message!(
+                Errno::EMFILE,
+                "the `RLIMIT_NOFILE` limit on the number of file descriptors is reached"
+            );
+        }
+        Ok(fd)
+    }
+
+    fn put_entry(&mut self, fd: usize, entry: FileTableEntry) -> Option<FileTableEntry> {
+        self.fd_bitmap.set(fd);
+        self.lookup.set(fd, Some(&entry.file));
+        self.table.put_at(fd, entry)
+    }
+
+    fn remove_entry(&mut self, fd: usize) -> Option<FileTableEntry> {
+        let removed_entry = self.table.remove(fd)?;
+        self.fd_bitmap.clear(fd);
+        self.lookup.set(fd, None);
+        Some(removed_entry)
+    }
 }
 
 impl Default for FileTable {
@@ -208,8 +260,15 @@ impl Default for FileTable {
 
 impl Clone for FileTable {
     fn clone(&self) -> Self {
+        let lookup = FileLookup::new();
+        for (fd, entry) in self.table.idxes_and_items() {
+            lookup.set(fd, Some(&entry.file));
+        }
+
         Self {
             table: self.table.clone(),
+            fd_bitmap: self.fd_bitmap.clone(),
+            lookup: Arc::new(lookup),
             subject: Subject::new(),
         }
     }
@@ -218,13 +277,171 @@ impl Clone for FileTable {
 impl Drop for FileTable {
     fn drop(&mut self) {
         // Closes all files first.
-        self.close_files(|_| true);
+        self.close_files(0..usize::MAX, |_| true);
 
         let events = FdEvents::DropFileTable;
         self.subject.notify_observers(&events);
     }
 }
 
+/// Returns the `RLIMIT_NOFILE` resource limit of the current process.
+fn nofile_limit() -> usize {
+    let Some(process) = Process::current() else {
+        return usize::MAX;
+    };
+    let limit = process
+        .resource_limits()
+        .get_rlimit(ResourceType::RLIMIT_NOFILE)
+        .get_cur();
+    limit.try_into().unwrap_or(usize::MAX)
+}
+
+/// A bitmap of the allocated file descriptors.
+#[derive(Clone)]
+struct FdBitmap {
+    words: Vec<u64>,
+    /// A file descriptor such that all the file descriptors below it are allocated.
+    ///
+    /// Since the lowest-numbered available file descriptor is allocated in most cases, searching
+    /// from this file descriptor makes the allocation O(1) for typical workloads.
+    next_fd: usize,
+}
+
+impl FdBitmap {
+    const BITS_PER_WORD: usize = u64::BITS as usize;
+
+    const fn new() -> Self {
+        Self {
+            words: Vec::new(),
+            next_fd: 0,
+        }
+    }
+
+    /// Returns the lowest-numbered free file descriptor equal to or greater than `start`.
+    fn first_free_from(&self, start: usize) -> usize {
+        let start = start.max(self.next_fd);
+
+        let mut word_index = start / Self::BITS_PER_WORD;
+        let mut mask = u64::MAX << (start % Self::BITS_PER_WORD);
+        while let Some(word) = self.words.get(word_index) {
+            let free_bits = !word & mask;
+            if free_bits != 0 {
+                return word_index * Self::BITS_PER_WORD + free_bits.trailing_zeros() as usize;
+            }
+            word_index += 1;
+            mask = u64::MAX;
+        }
+
+        start.max(word_index * Self::BITS_PER_WORD)
+    }
+
+    fn set(&mut self, fd: usize) {
+        let word_index = fd / Self::BITS_PER_WORD;
+        if word_index >= self.words.len() {
+            self.words.resize(word_index + 1, 0);
+        }
+        self.words[word_index] |= 1 << (fd % Self::BITS_PER_WORD);
+
+        if fd == self.next_fd {
+            self.next_fd = fd + 1;
+        }
+    }
+
+    fn clear(&mut self, fd: usize) {
+        let Some(word) = self.words.get_mut(fd / Self::BITS_PER_WORD) else {
+            return;
+        };
+        *word &= !(1 << (fd % Self::BITS_PER_WORD));
+
+        if fd < self.next_fd {
+            self.next_fd = fd;
+        }
+    }
+
+    /// Returns an iterator over the allocated file descriptors in the range.
+    fn allocated_fds(&self, range: Range<usize>) -> impl Iterator<Item = usize> + '_ {
+        let end = range.end.min(self.words.len() * Self::BITS_PER_WORD);
+        (range.start..end).filter(|fd| {
+            self.words[fd / Self::BITS_PER_WORD] & (1 << (fd % Self::BITS_PER_WORD)) != 0
+        })
+    }
+}
+
+/// A lock-free view of the files in a [`FileTable`].
+///
+/// The view is updated by the file table, while it can be read without holding the lock of the
+/// file table. This avoids the contention on the lock when the file table is shared by many
+/// threads, e.g., a server accepting connections in some threads while serving the connections in
+/// other threads.
+///
+/// The view only holds weak references to the files, so closing a file descriptor still releases
+/// the file immediately.
+pub struct FileLookup {
+    files: Rcu<Box<FileArray>>,
+}
+
+/// The array of the files, indexed by the file descriptors.
+struct FileArray(Box<[RcuOption<Box<Weak<dyn FileLike>>>]>);
+
+impl FileLookup {
+    /// The minimum capacity of the array when it grows.
+    const MIN_CAPACITY: usize = 64;
+
+    fn new() -> Self {
+        Self {
+            files: Rcu::new(Box::new(FileArray(Box::new([])))),
+        }
+    }
+
+    /// Gets the file of the file descriptor.
+    pub fn get(&self, fd: FileDesc) -> Result<Arc<dyn FileLike>> {
+        let guard = disable_preempt();
+        let files = self.files.read_with(&guard);
+        usize::try_from(fd)
+            .ok()
+            .and_then(|fd| files.0.get(fd))
+            .and_then(|slot| slot.read_with(&guard))
+            .and_then(|file| file.upgrade())
+            .ok_or(Error::with_message(Errno::EBADF, "fd not exits"))
+    }
+
+    /// Sets the file of the file descriptor.
+    ///
+    /// This method must be called with exclusive access to the file table, so there is at most
+    /// one writer at a time.
+    fn set(&self, fd: usize, file: Option<&Arc<dyn FileLike>>) {
+        let new_file = file.map(|file| Box::new(Arc::downgrade(file)));
+
+        let guard = disable_preempt();
+        let files = self.files.read_with(&guard);
+        if let Some(slot) = files.0.get(fd) {
+            slot.update(new_file);
+            return;
+        }
+        if new_file.is_none() {
+            return;
+        }
+
+        // Grow the array. Readers may still be reading the old array, which will be freed after
+        // they finish reading.
+        let new_len = (fd + 1).next_power_of_two().max(Self::MIN_CAPACITY);
+        let new_files = (0..new_len)
+            .map(|index| {
+                let file = files
+                    .0
+                    .get(index)
+                    .and_then(|slot| slot.read_with(&guard))
+                    .map(|file| Box::new((*file).clone()));
+                RcuOption::new(file)
+            })
+            .collect::<Box<[_]>>();
+        new_files[fd].update(new_file);
+        drop(guard);
+
+        self.files.update(Box::new(FileArray(new_files)));
+    }
+}
+
 /// A helper trait that provides methods to operate the file table.
 pub trait WithFileTable {
     /// Calls `f` with the file table.
@@ -255,9 +472,8 @@ impl WithFileTable for FileTableRefMut<'_> {
 /// If the file table is not shared with another thread, this macro will be free of locks
 /// ([`RwArc::read`]) and free of reference counting ([`Arc::clone`]).
 ///
-/// If the file table is shared, the read lock is taken, the file is cloned, and then the read lock
-/// is released. Cloning and releasing the lock is necessary because we cannot hold such locks when
-/// operating on files, since many operations on files can block.
+/// If the file table is shared, the file is looked up via the [`FileLookup`] of the file table,
+/// which is free of locks but requires cloning the file.
 ///
 /// Note: This has to be a macro due to a limitation in the Rust borrow check implementation. Once
 /// <https://github.com/rust-lang/rust/issues/58910> is fixed, we can try to convert this macro to
@@ -271,20 +487,22 @@ macro_rules! get_file_fast {
 
         use ostd::sync::RwArc;
         use $crate::{
-            fs::file_table::{FileDesc, FileTable},
+            fs::file_table::{FileDesc, FileLookup, FileTable},
             process::posix_thread::FileTableRefMut,
         };
 
         let file_table: &mut FileTableRefMut<'_> = $file_table;
-        let file_table: &mut RwArc<FileTable> = file_table.unwrap();
+        let (file_table, lookup): (&mut RwArc<FileTable>, &FileLookup) =
+            file_table.unwrap_with_lookup();
         let file_desc: FileDesc = $file_desc;
 
         if let Some(inner) = file_table.get() {
             // Fast path: The file table is not shared, we can get the file in a lockless way.
             Cow::Borrowed(inner.get_file(file_desc)?)
         } else {
-            // Slow path: The file table is shared, we need to hold the lock and clone the file.
-            Cow::Owned(file_table.read().get_file(file_desc)?.clone())
+            // Slow path: The file table is shared, we need to clone the file. The file is looked
+            // up without holding the lock of the file table.
+            Cow::Owned(lookup.get(file_desc)?)
         }
     }};
 }
@@ -320,29 +538,30 @@ impl FileTableEntry {
         &self.file
     }
 
-    pub fn owner(&self) -> Option<Pid> {
-        self.owner.as_ref().map(|(pid, _)| *pid)
+    pub fn owner(&self) -> Option<OwnerId> {
+        self.owner.as_ref().map(|(id, _)| *id)
     }
 
     /// Set a process (group) as owner of the file descriptor.
     ///
-    /// Such that this process (group) will receive `SIGIO` and `SIGURG` signals
-    /// for I/O events on the file descriptor, if `O_ASYNC` status flag is set
-    /// on this file.
-    pub fn set_owner(&mut self, owner: Option<&Arc<Process>>) -> Result<()> {
-        let Some(process) = owner else {
+    /// Such that this process (group) will receive `SIGIO` signals for I/O
+    /// events on the file descriptor, if `O_ASYNC` status flag is set on this
+    /// file, and `SIGURG` signals for urgent data on the socket.
+    pub fn set_owner(&mut self, owner: Option<FileOwner>) -> Result<()> {
+        let Some(owner) = owner else {
             self.owner = None;
             return Ok(());
         };
 
-        let mut poller = PollAdaptor::with_observer(OwnerObserver::new(
-            self.file.clone(),
-            Arc::downgrade(process),
-        ));
-        self.file
-            .poll(IoEvents::IN | IoEvents::OUT, Some(poller.as_handle_mut()));
+        let id = owner.id();
+        let mut poller =
+            PollAdaptor::with_observer(OwnerObserver::new(self.file.clone(), owner.downgrade()));
+        self.file.poll(
+            IoEvents::IN | IoEvents::OUT | IoEvents::PRI,
+            Some(poller.as_handle_mut()),
+        );
 
-        self.owner = Some((process.pid(), poller));
+        self.owner = Some((id, poller));
 
         Ok(())
     }
@@ -390,25 +609,163 @@ bitflags! {
     }
 }
 
-type Owner = (Pid, PollAdaptor<OwnerObserver>);
+bitflags! {
+    /// The flags that are honored by all the system calls creating file descriptors.
+    ///
+    /// On Linux, the `*_CLOEXEC` flags (e.g., `O_CLOEXEC`, `SOCK_CLOEXEC`, `EFD_CLOEXEC`,
+    /// `TFD_CLOEXEC`, `SFD_CLOEXEC`, and `EPOLL_CLOEXEC`) all share the value of `O_CLOEXEC`,
+    /// and the `*_NONBLOCK` flags all share the value of `O_NONBLOCK`. So the flags can be
+    /// extracted from the raw flags of any such system call with
+    /// [`FdCreationFlags::from_bits_truncate`].
+    pub struct FdCreationFlags: u32 {
+        /// Sets `O_NONBLOCK` on the new file.
+        const NONBLOCK = StatusFlags::O_NONBLOCK.bits();
+        /// Sets `FD_CLOEXEC` on the new file descriptor.
+        const CLOEXEC = CreationFlags::O_CLOEXEC.bits();
+    }
+}
+
+impl FdCreationFlags {
+    /// Returns the flags of the new file descriptor.
+    pub fn fd_flags(&self) -> FdFlags {
+        if self.contains(Self::CLOEXEC) {
+            FdFlags::CLOEXEC
+        } else {
+            FdFlags::empty()
+        }
+    }
+
+    /// Returns whether the new file should be nonblocking.
+    pub fn is_nonblocking(&self) -> bool {
+        self.contains(Self::NONBLOCK)
+    }
+
+    /// Applies the flags to the new file and returns the flags of its file descriptor.
+    ///
+    /// This method should be called before the file is inserted into the file table, since
+    /// setting the status flags of a file may sleep.
+    pub fn apply_to(&self, file: &dyn FileLike) -> Result<FdFlags> {
+        if self.is_nonblocking() && !file.status_flags().contains(StatusFlags::O_NONBLOCK) {
+            file.update_status_flags(StatusFlags::O_NONBLOCK, StatusFlags::O_NONBLOCK)?;
+        }
+        Ok(self.fd_flags())
+    }
+}
+
+/// The owner of a file descriptor, which receives `SIGIO` and `SIGURG` signals.
+pub enum FileOwner {
+    Thread(Arc<Thread>),
+    Process(Arc<Process>),
+    ProcessGroup(Arc<ProcessGroup>),
+}
+
+impl FileOwner {
+    fn id(&self) -> OwnerId {
+        match self {
+            Self::Thread(thread) => OwnerId::Thread(thread.as_posix_thread().unwrap().tid()),
+            Self::Process(process) => OwnerId::Process(process.pid()),
+            Self::ProcessGroup(process_group) => OwnerId::ProcessGroup(process_group.pgid()),
+        }
+    }
+
+    fn downgrade(&self) -> WeakFileOwner {
+        match self {
+            Self::Thread(thread) => WeakFileOwner::Thread(Arc::downgrade(thread)),
+            Self::Process(process) => WeakFileOwner::Process(Arc::downgrade(process)),
+            Self::ProcessGroup(process_group) => {
+                WeakFileOwner::ProcessGroup(Arc::downgrade(process_group))
+            }
+        }
+    }
+}
+
+/// The ID of the owner of a file descriptor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OwnerId {
+    Thread(Tid),
+    Process(Pid),
+    ProcessGroup(Pgid),
+}
+
+enum WeakFileOwner {
+    Thread(Weak<Thread>),
+    Process(Weak<Process>),
+    ProcessGroup(Weak<ProcessGroup>),
+}
+
+impl WeakFileOwner {
+    fn send_signal(&self, signal: KernelSignal) {
+        match self {
+            Self::Thread(thread) => {
+                if let Some(thread) = thread.upgrade() {
+                    thread
+                        .as_posix_thread()
+                        .unwrap()
+                        .enqueue_signal(Box::new(signal));
+                }
+            }
+            Self::Process(process) => {
+                if let Some(process) = process.upgrade() {
+                    process.enqueue_signal(signal);
+                }
+            }
+            Self::ProcessGroup(process_group) => {
+                if let Some(process_group) = process_group.upgrade() {
+                    process_group.broadcast_signal(signal);
+                }
+            }
+        }
+    }
+}
+
+type Owner = (OwnerId, PollAdaptor<OwnerObserver>);
 
 struct OwnerObserver {
     file: Arc<dyn FileLike>,
-    owner: Weak<Process>,
+    owner: WeakFileOwner,
 }
 
 impl OwnerObserver {
-    pub fn new(file: Arc<dyn FileLike>, owner: Weak<Process>) -> Self {
+    pub fn new(file: Arc<dyn FileLike>, owner: WeakFileOwner) -> Self {
         Self { file, owner }
     }
 }
 
 impl Observer<IoEvents> for OwnerObserver {
-    fn on_events(&self, _events: &IoEvents) {
-        if self.file.status_flags().contains(StatusFlags::O_ASYNC)
-            && let Some(process) = self.owner.upgrade()
-        {
-            process.enqueue_signal(KernelSignal::new(SIGIO));
+    fn on_events(&self, events: &IoEvents) {
+        // Urgent data on sockets is reported regardless of `O_ASYNC`, as Linux does.
+        if events.contains(IoEvents::PRI) && self.file.as_socket().is_some() {
+            self.owner.send_signal(KernelSignal::new(SIGURG));
+        }
+
+        if self.file.status_flags().contains(StatusFlags::O_ASYNC) {
+            self.owner.send_signal(KernelSignal::new(SIGIO));
         }
     }
 }
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn fd_bitmap_alloc() {
+        let mut bitmap = FdBitmap::new();
+        for fd in 0..70 {
+            assert_eq!(bitmap.first_free_from(0), fd);
+            bitmap.set(fd);
+        }
+
+        bitmap.clear(3);
+        bitmap.clear(65);
+        assert_eq!(bitmap.first_free_from(0), 3);
+        assert_eq!(bitmap.first_free_from(4), 65);
+        assert_eq!(bitmap.first_free_from(66), 70);
+        assert_eq!(bitmap.first_free_from(200), 200);
+
+        let fds: Vec<usize> = bitmap.allocated_fds(60..usize::MAX).collect();
+        assert_eq!(fds, [60, 61, 62, 63, 64, 66, 67, 68, 69]);
+    }
+}
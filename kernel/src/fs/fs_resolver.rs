@@ -85,19 +85,11 @@ impl FsResolver {
         let inode_type = inode.type_();
         let creation_flags = &open_args.creation_flags;
 
-        match inode_type {
-            InodeType::NamedPipe => {
-                warn!("NamedPipe doesn't support additional operation when opening.");
-                debug!("Open NamedPipe with args: {open_args:?}.");
-            }
-            InodeType::SymLink => {
-                if creation_flags.contains(CreationFlags::O_NOFOLLOW)
-                    && !open_args.status_flags.contains(StatusFlags::O_PATH)
-                {
-                    return_errno_with_message!(Errno::ELOOP, "file is a symlink");
-                }
-            }
-            _ => {}
+        if inode_type == InodeType::SymLink
+            && creation_flags.contains(CreationFlags::O_NOFOLLOW)
+            && !open_args.status_flags.contains(StatusFlags::O_PATH)
+        {
+            return_errno_with_message!(Errno::ELOOP, "file is a symlink");
         }
 
         if creation_flags.contains(CreationFlags::O_CREAT)
@@ -112,7 +104,11 @@ impl FsResolver {
             );
         }
 
-        if creation_flags.contains(CreationFlags::O_TRUNC) {
+        // Like Linux, `O_TRUNC` is ignored for FIFOs and device files.
+        if creation_flags.contains(CreationFlags::O_TRUNC)
+            && inode_type != InodeType::NamedPipe
+            && !inode_type.is_device()
+        {
             target_dentry.resize(0)?;
         }
         InodeHandle::new(target_dentry, open_args.access_mode, open_args.status_flags)
@@ -4,7 +4,7 @@ use aster_rights::TRights;
 use inherit_methods_macro::inherit_methods;
 
 use super::*;
-use crate::{prelude::*, process::signal::Pollable};
+use crate::{fs::named_pipe::open_named_pipe, prelude::*, process::signal::Pollable};
 
 impl InodeHandle<Rights> {
     pub fn new(dentry: Dentry, access_mode: AccessMode, status_flags: StatusFlags) -> Result<Self> {
@@ -25,16 +25,22 @@ impl InodeHandle<Rights> {
 
         let file_io = if let Some(device) = inode.as_device() {
             device.open()?
+        } else if inode.type_() == InodeType::NamedPipe
+            && !status_flags.contains(StatusFlags::O_PATH)
+        {
+            Some(open_named_pipe(inode, access_mode, status_flags)?)
         } else {
-            None
+            inode.open()?
         };
 
+        dentry.notify_event(InotifyMask::IN_OPEN);
         let inner = Arc::new(InodeHandle_ {
             dentry,
             file_io,
             offset: Mutex::new(0),
             access_mode,
             status_flags: AtomicU32::new(status_flags.bits()),
+            readahead_hint: AtomicReadaheadHint::new(ReadaheadHint::Normal),
         });
         Ok(Self(inner, Rights::from(access_mode)))
     }
@@ -69,6 +75,10 @@ impl Pollable for InodeHandle<Rights> {
 #[inherit_methods(from = "self.0")]
 impl FileLike for InodeHandle<Rights> {
     fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32>;
+    fn fadvise(&self, advice: FileAdvice, offset: usize, len: usize) -> Result<()>;
+    fn mmap_vmo(&self, offset: usize, len: usize) -> Result<(Vmo, usize)>;
+    fn pipe_capacity(&self) -> Result<usize>;
+    fn set_pipe_capacity(&self, capacity: usize) -> Result<()>;
     fn status_flags(&self) -> StatusFlags;
     fn access_mode(&self) -> AccessMode;
     fn metadata(&self) -> Metadata;
@@ -120,6 +130,11 @@ impl FileLike for InodeHandle<Rights> {
         Ok(())
     }
 
+    fn update_status_flags(&self, mask: StatusFlags, new_flags: StatusFlags) -> Result<()> {
+        self.0.update_status_flags(mask, new_flags);
+        Ok(())
+    }
+
     fn fallocate(&self, mode: FallocMode, offset: usize, len: usize) -> Result<()> {
         if !self.1.contains(Rights::WRITE) {
             return_errno_with_message!(Errno::EBADF, "file is not writable");
@@ -9,6 +9,7 @@ mod static_cap;
 
 use core::sync::atomic::{AtomicU32, Ordering};
 
+use align_ext::AlignExt;
 use aster_rights::Rights;
 use inherit_methods_macro::inherit_methods;
 
@@ -16,18 +17,21 @@ use crate::{
     events::IoEvents,
     fs::{
         file_handle::FileLike,
+        inotify::InotifyMask,
         path::Dentry,
         utils::{
-            AccessMode, DirentVisitor, FallocMode, FileRange, FlockItem, FlockList, InodeMode,
-            InodeType, IoctlCmd, Metadata, RangeLockItem, RangeLockItemBuilder, RangeLockList,
-            RangeLockType, SeekFrom, StatusFlags, OFFSET_MAX,
+            with_readahead_hint, AccessMode, AtomicReadaheadHint, DirentVisitor, FallocMode,
+            FileAdvice, FileRange, FlockItem, FlockList, InodeMode, InodeType, IoctlCmd, Metadata,
+            RangeLockItem, RangeLockItemBuilder, RangeLockList, RangeLockType, ReadaheadHint,
+            SeekFrom, StatusFlags, OFFSET_MAX,
         },
     },
     prelude::*,
     process::{
-        signal::{PollHandle, Pollable},
+        signal::{do_io_nowait, PollHandle, Pollable},
         Gid, Uid,
     },
+    vm::vmo::Vmo,
 };
 
 #[derive(Debug)]
@@ -42,11 +46,18 @@ struct InodeHandle_ {
     offset: Mutex<usize>,
     access_mode: AccessMode,
     status_flags: AtomicU32,
+    /// The readahead hint advised by `fadvise`.
+    readahead_hint: AtomicReadaheadHint,
 }
 
 impl InodeHandle_ {
     pub fn read(&self, writer: &mut VmWriter) -> Result<usize> {
-        if let Some(ref file_io) = self.file_io {
+        if let Some(ref file_io) = self.file_io
+            && !file_io.is_offset_aware()
+        {
+            if self.status_flags().contains(StatusFlags::O_NONBLOCK) {
+                return do_io_nowait(|| file_io.read(writer));
+            }
             return file_io.read(writer);
         }
 
@@ -63,7 +74,12 @@ impl InodeHandle_ {
     }
 
     pub fn write(&self, reader: &mut VmReader) -> Result<usize> {
-        if let Some(ref file_io) = self.file_io {
+        if let Some(ref file_io) = self.file_io
+            && !file_io.is_offset_aware()
+        {
+            if self.status_flags().contains(StatusFlags::O_NONBLOCK) {
+                return do_io_nowait(|| file_io.write(reader));
+            }
             return file_io.write(reader);
         }
 
@@ -85,19 +101,30 @@ impl InodeHandle_ {
 
     pub fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
         if let Some(ref file_io) = self.file_io {
-            todo!("support read_at for FileIo");
+            if file_io.is_offset_aware() {
+                return file_io.read_at(offset, writer);
+            }
+            return_errno_with_message!(Errno::ESPIPE, "the file does not support read_at");
         }
 
-        if self.status_flags().contains(StatusFlags::O_DIRECT) {
-            self.dentry.inode().read_direct_at(offset, writer)
+        let len = if self.status_flags().contains(StatusFlags::O_DIRECT) {
+            self.dentry.inode().read_direct_at(offset, writer)?
         } else {
-            self.dentry.inode().read_at(offset, writer)
+            let hint = self.readahead_hint.load(Ordering::Relaxed);
+            with_readahead_hint(hint, || self.dentry.inode().read_at(offset, writer))?
+        };
+        if len > 0 {
+            self.dentry.notify_event(InotifyMask::IN_ACCESS);
         }
+        Ok(len)
     }
 
     pub fn write_at(&self, mut offset: usize, reader: &mut VmReader) -> Result<usize> {
         if let Some(ref file_io) = self.file_io {
-            todo!("support write_at for FileIo");
+            if file_io.is_offset_aware() {
+                return file_io.write_at(offset, reader);
+            }
+            return_errno_with_message!(Errno::ESPIPE, "the file does not support write_at");
         }
 
         let status_flags = self.status_flags();
@@ -106,14 +133,24 @@ impl InodeHandle_ {
             offset = self.dentry.size();
         }
 
-        if status_flags.contains(StatusFlags::O_DIRECT) {
-            self.dentry.inode().write_direct_at(offset, reader)
+        let len = if status_flags.contains(StatusFlags::O_DIRECT) {
+            self.dentry.inode().write_direct_at(offset, reader)?
         } else {
-            self.dentry.inode().write_at(offset, reader)
+            self.dentry.inode().write_at(offset, reader)?
+        };
+        if len > 0 {
+            self.dentry.notify_event(InotifyMask::IN_MODIFY);
         }
+        Ok(len)
     }
 
     pub fn seek(&self, pos: SeekFrom) -> Result<usize> {
+        if let Some(ref file_io) = self.file_io
+            && let Some(res) = file_io.seek(pos)
+        {
+            return res;
+        }
+
         let mut offset = self.offset.lock();
         let new_offset: isize = match pos {
             SeekFrom::Start(off /* as usize */) => {
@@ -132,6 +169,13 @@ impl InodeHandle_ {
             SeekFrom::Current(off /* as isize */) => (*offset as isize)
                 .checked_add(off)
                 .ok_or_else(|| Error::with_message(Errno::EOVERFLOW, "file offset overflow"))?,
+            // Holes are not tracked, so the whole file is treated as data followed by a hole at
+            // the end.
+            SeekFrom::Data(off) | SeekFrom::Hole(off) if off >= self.dentry.size() => {
+                return_errno_with_message!(Errno::ENXIO, "file offset is beyond the end");
+            }
+            SeekFrom::Data(off) => off as isize,
+            SeekFrom::Hole(_) => self.dentry.size() as isize,
         };
         if new_offset < 0 {
             return_errno_with_message!(Errno::EINVAL, "file offset must not be negative");
@@ -168,6 +212,15 @@ impl InodeHandle_ {
             .store(new_status_flags.bits(), Ordering::Relaxed);
     }
 
+    pub fn update_status_flags(&self, mask: StatusFlags, new_flags: StatusFlags) {
+        let new_bits = (new_flags & mask).bits();
+        let _ = self
+            .status_flags
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((bits & !mask.bits()) | new_bits)
+            });
+    }
+
     pub fn readdir(&self, visitor: &mut dyn DirentVisitor) -> Result<usize> {
         let mut offset = self.offset.lock();
         let read_cnt = self.dentry.inode().readdir_at(*offset, visitor)?;
@@ -207,6 +260,40 @@ impl InodeHandle_ {
         self.dentry.inode().fallocate(mode, offset, len)
     }
 
+    fn fadvise(&self, advice: FileAdvice, offset: usize, len: usize) -> Result<()> {
+        let inode = self.dentry.inode();
+        if inode.type_() == InodeType::NamedPipe {
+            return_errno_with_message!(Errno::ESPIPE, "fadvise is not supported on FIFOs");
+        }
+
+        let hint = match advice {
+            FileAdvice::Normal => ReadaheadHint::Normal,
+            FileAdvice::Random => ReadaheadHint::Random,
+            FileAdvice::Sequential => ReadaheadHint::Sequential,
+            FileAdvice::DontNeed => {
+                let Some(page_cache) = inode.page_cache() else {
+                    return Ok(());
+                };
+                // Like Linux, the partial pages at both ends of the range are kept, except for
+                // the last page of the file.
+                let start = offset.align_up(PAGE_SIZE);
+                let end = match offset.checked_add(len) {
+                    Some(end) if len != 0 && end < inode.size() => end.align_down(PAGE_SIZE),
+                    _ => usize::MAX,
+                }
+                .min(page_cache.size());
+                if start >= end {
+                    return Ok(());
+                }
+                return page_cache.decommit_clean(start..end);
+            }
+            // TODO: Start the readahead of the range for `WillNeed`.
+            FileAdvice::WillNeed | FileAdvice::NoReuse => return Ok(()),
+        };
+        self.readahead_hint.store(hint, Ordering::Relaxed);
+        Ok(())
+    }
+
     fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
         if let Some(ref file_io) = self.file_io {
             return file_io.ioctl(cmd, arg);
@@ -215,6 +302,30 @@ impl InodeHandle_ {
         self.dentry.inode().ioctl(cmd, arg)
     }
 
+    fn mmap_vmo(&self, offset: usize, len: usize) -> Result<(Vmo, usize)> {
+        if let Some(ref file_io) = self.file_io {
+            return file_io.mmap_vmo(offset, len);
+        }
+
+        return_errno_with_message!(Errno::EBADF, "File does not have page cache");
+    }
+
+    fn pipe_capacity(&self) -> Result<usize> {
+        if let Some(ref file_io) = self.file_io {
+            return file_io.pipe_capacity();
+        }
+
+        return_errno_with_message!(Errno::EBADF, "the file is not a pipe");
+    }
+
+    fn set_pipe_capacity(&self, capacity: usize) -> Result<()> {
+        if let Some(ref file_io) = self.file_io {
+            return file_io.set_pipe_capacity(capacity);
+        }
+
+        return_errno_with_message!(Errno::EBADF, "the file is not a pipe");
+    }
+
     fn test_range_lock(&self, lock: RangeLockItem) -> Result<RangeLockItem> {
         let mut req_lock = lock.clone();
         if let Some(extension) = self.dentry.inode().extension() {
@@ -330,6 +441,17 @@ impl InodeHandle_ {
     pub fn set_group(&self, gid: Gid) -> Result<()>;
 }
 
+impl Drop for InodeHandle_ {
+    fn drop(&mut self) {
+        let mask = if self.access_mode.is_writable() {
+            InotifyMask::IN_CLOSE_WRITE
+        } else {
+            InotifyMask::IN_CLOSE_NOWRITE
+        };
+        self.dentry.notify_event(mask);
+    }
+}
+
 impl Debug for InodeHandle_ {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         f.debug_struct("InodeHandle_")
@@ -384,7 +506,57 @@ pub trait FileIo: Pollable + Send + Sync + 'static {
 
     fn write(&self, reader: &mut VmReader) -> Result<usize>;
 
+    /// Returns whether the reads and writes depend on the file offset.
+    ///
+    /// If this method returns true, the reads and writes go through [`FileIo::read_at`] and
+    /// [`FileIo::write_at`] with the offset of the opened file, which is advanced as for regular
+    /// files. Otherwise, [`FileIo::read`] and [`FileIo::write`] are used and the offset is
+    /// ignored.
+    fn is_offset_aware(&self) -> bool {
+        false
+    }
+
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        return_errno_with_message!(Errno::ESPIPE, "read_at is not supported");
+    }
+
+    /// Writes at the offset if the file is offset-aware.
+    ///
+    /// The default implementation ignores the offset.
+    fn write_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        self.write(reader)
+    }
+
+    /// Repositions the file if the file keeps its own position.
+    ///
+    /// If this method returns `None`, the offset of the opened file is changed as for regular
+    /// files.
+    fn seek(&self, pos: SeekFrom) -> Option<Result<usize>> {
+        None
+    }
+
     fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
         return_errno_with_message!(Errno::EINVAL, "ioctl is not supported");
     }
+
+    /// Returns the VMO that backs the memory mappings of the file.
+    ///
+    /// See [`FileLike::mmap_vmo`].
+    fn mmap_vmo(&self, offset: usize, len: usize) -> Result<(Vmo, usize)> {
+        return_errno_with_message!(Errno::ENODEV, "mmap is not supported");
+    }
+
+    /// Returns the capacity of the pipe if the file is a named pipe.
+    ///
+    /// See [`FileLike::pipe_capacity`].
+    fn pipe_capacity(&self) -> Result<usize> {
+        return_errno_with_message!(Errno::EBADF, "the file is not a pipe");
+    }
+
+    /// Sets the capacity of the pipe if the file is a named pipe.
+    ///
+    /// See [`FileLike::set_pipe_capacity`].
+    fn set_pipe_capacity(&self, capacity: usize) -> Result<()> {
+        return_errno_with_message!(Errno::EBADF, "the file is not a pipe");
+    }
 }
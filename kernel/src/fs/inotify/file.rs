@@ -0,0 +1,349 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use align_ext::AlignExt;
+
+use super::{InodeKey, InotifyMask, Watch, NR_WATCHED_INODES, WATCHES};
+use crate::{
+    events::IoEvents,
+    fs::{
+        file_handle::FileLike,
+        utils::{Inode, InodeMode, InodeType, IoctlCmd, Metadata, StatusFlags},
+    },
+    prelude::*,
+    process::{
+        signal::{PollHandle, Pollable, Pollee},
+        Gid, Uid,
+    },
+    time::clocks::RealTimeClock,
+};
+
+/// The maximum number of the events in the queue of an instance, which is the same as the
+/// default value of `/proc/sys/fs/inotify/max_queued_events` in Linux.
+const MAX_QUEUED_EVENTS: usize = 16384;
+
+/// The maximum number of the watches of an instance, which is the same as the default value of
+/// `/proc/sys/fs/inotify/max_user_watches` in Linux.
+const MAX_WATCHES: usize = 8192;
+
+/// The size of `struct inotify_event` without the name.
+const EVENT_HEADER_SIZE: usize = 16;
+
+/// An inotify instance.
+pub struct InotifyFile {
+    inner: Mutex<Inner>,
+    pollee: Pollee,
+    is_nonblocking: AtomicBool,
+    this: Weak<InotifyFile>,
+}
+
+struct Inner {
+    events: VecDeque<InotifyEvent>,
+    /// The total size of the queued events in `struct inotify_event`.
+    nr_queued_bytes: usize,
+    /// The watched inodes, indexed by the watch descriptors.
+    watches: BTreeMap<u32, InodeKey>,
+    next_wd: u32,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct InotifyEvent {
+    wd: i32,
+    mask: InotifyMask,
+    cookie: u32,
+    name: Option<String>,
+}
+
+impl InotifyEvent {
+    /// Returns the length of the name, including the null terminator and the padding.
+    fn name_len(&self) -> usize {
+        self.name
+            .as_ref()
+            .map_or(0, |name| (name.len() + 1).align_up(EVENT_HEADER_SIZE))
+    }
+
+    fn size(&self) -> usize {
+        EVENT_HEADER_SIZE + self.name_len()
+    }
+
+    fn write_to(&self, writer: &mut VmWriter) -> Result<()> {
+        let name_len = self.name_len();
+        writer.write_val(&self.wd)?;
+        writer.write_val(&self.mask.bits())?;
+        writer.write_val(&self.cookie)?;
+        writer.write_val(&(name_len as u32))?;
+
+        if let Some(name) = self.name.as_ref() {
+            writer.write_fallible(&mut name.as_bytes().into())?;
+            writer.fill_zeros(name_len - name.len())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl InotifyFile {
+    /// Creates a new inotify instance.
+    pub fn new(is_nonblocking: bool) -> Arc<Self> {
+        Arc::new_cyclic(|weak_self| Self {
+            inner: Mutex::new(Inner {
+                events: VecDeque::new(),
+                nr_queued_bytes: 0,
+                watches: BTreeMap::new(),
+                next_wd: 1,
+            }),
+            pollee: Pollee::new(),
+            is_nonblocking: AtomicBool::new(is_nonblocking),
+            this: weak_self.clone(),
+        })
+    }
+
+    /// Watches the inode for the events in the mask, and returns the watch descriptor.
+    ///
+    /// If the inode is already watched by this instance, the watch is updated and the same watch
+    /// descriptor is returned.
+    pub fn add_watch(&self, inode: Arc<dyn Inode>, mask: InotifyMask) -> Result<u32> {
+        if !mask.intersects(InotifyMask::IN_ALL_EVENTS) {
+            return_errno_with_message!(Errno::EINVAL, "no events are specified");
+        }
+        if mask.contains(InotifyMask::IN_MASK_ADD | InotifyMask::IN_MASK_CREATE) {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "IN_MASK_ADD and IN_MASK_CREATE cannot be both specified"
+            );
+        }
+        if mask.contains(InotifyMask::IN_ONLYDIR) && inode.type_() != InodeType::Dir {
+            return_errno_with_message!(Errno::ENOTDIR, "the inode is not a directory");
+        }
+
+        // Only the events and the options that affect the later events are kept.
+        let kept_mask = mask
+            & (InotifyMask::IN_ALL_EVENTS | InotifyMask::IN_EXCL_UNLINK | InotifyMask::IN_ONESHOT);
+        let key = InodeKey::new(inode.as_ref());
+
+        let mut watches = WATCHES.lock();
+        let mut inner = self.inner.lock();
+
+        let inode_watches = watches.entry(key).or_default();
+        if let Some(watch) = inode_watches
+            .iter_mut()
+            .find(|watch| watch.file.as_ptr() == self.this.as_ptr())
+        {
+            if mask.contains(InotifyMask::IN_MASK_CREATE) {
+                return_errno_with_message!(Errno::EEXIST, "the inode is already watched");
+            }
+            if mask.contains(InotifyMask::IN_MASK_ADD) {
+                watch.mask |= kept_mask;
+            } else {
+                watch.mask = kept_mask;
+            }
+            return Ok(watch.wd);
+        }
+
+        if inner.watches.len() >= MAX_WATCHES {
+            if inode_watches.is_empty() {
+                watches.remove(&key);
+            }
+            return_errno_with_message!(Errno::ENOSPC, "too many watches");
+        }
+
+        let wd = inner.next_wd;
+        inner.next_wd += 1;
+        inner.watches.insert(wd, key);
+
+        if inode_watches.is_empty() {
+            NR_WATCHED_INODES.fetch_add(1, Ordering::Relaxed);
+        }
+        inode_watches.push(Watch {
+            file: self.this.clone(),
+            wd,
+            mask: kept_mask,
+            _inode: inode,
+        });
+
+        Ok(wd)
+    }
+
+    /// Removes the watch, and queues an `IN_IGNORED` event for it.
+    pub fn rm_watch(&self, wd: u32) -> Result<()> {
+        let mut watches = WATCHES.lock();
+
+        let Some(key) = self.inner.lock().watches.get(&wd).copied() else {
+            return_errno_with_message!(Errno::EINVAL, "the watch descriptor is not valid");
+        };
+
+        let inode_watches = watches.get_mut(&key).unwrap();
+        inode_watches.retain(|watch| watch.wd != wd || watch.file.as_ptr() != self.this.as_ptr());
+        if inode_watches.is_empty() {
+            watches.remove(&key);
+            NR_WATCHED_INODES.fetch_sub(1, Ordering::Relaxed);
+        }
+
+        self.remove_watch_and_notify(wd);
+        Ok(())
+    }
+
+    /// Forgets the watch, and queues an `IN_IGNORED` event for it.
+    ///
+    /// The caller should remove the watch from the global table while holding its lock.
+    pub(super) fn remove_watch_and_notify(&self, wd: u32) {
+        if self.inner.lock().watches.remove(&wd).is_some() {
+            self.push_event(wd as i32, InotifyMask::IN_IGNORED, 0, None);
+        }
+    }
+
+    /// Queues an event.
+    ///
+    /// The event is merged with the last event in the queue if they are the same. If the queue
+    /// is full, an `IN_Q_OVERFLOW` event is queued instead.
+    pub(super) fn push_event(&self, wd: i32, mask: InotifyMask, cookie: u32, name: Option<&str>) {
+        let mut inner = self.inner.lock();
+
+        let mut event = InotifyEvent {
+            wd,
+            mask,
+            cookie,
+            name: name.map(String::from),
+        };
+        if inner.events.len() >= MAX_QUEUED_EVENTS - 1 {
+            event = InotifyEvent {
+                wd: -1,
+                mask: InotifyMask::IN_Q_OVERFLOW,
+                cookie: 0,
+                name: None,
+            };
+            if inner.events.len() == MAX_QUEUED_EVENTS {
+                return;
+            }
+        }
+        if inner.events.back() == Some(&event) {
+            return;
+        }
+
+        inner.nr_queued_bytes += event.size();
+        inner.events.push_back(event);
+        drop(inner);
+
+        self.pollee.notify(IoEvents::IN);
+    }
+
+    fn try_read(&self, writer: &mut VmWriter) -> Result<usize> {
+        let mut inner = self.inner.lock();
+
+        let Some(first_event) = inner.events.front() else {
+            return_errno_with_message!(Errno::EAGAIN, "no events are queued");
+        };
+        if first_event.size() > writer.avail() {
+            return_errno_with_message!(Errno::EINVAL, "the buffer is too small for the event");
+        }
+
+        let mut read_len = 0;
+        while let Some(event) = inner.events.front() {
+            let event_size = event.size();
+            if event_size > writer.avail() {
+                break;
+            }
+            event.write_to(writer)?;
+
+            inner.events.pop_front();
+            inner.nr_queued_bytes -= event_size;
+            read_len += event_size;
+        }
+
+        self.pollee.invalidate();
+        Ok(read_len)
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        if self.inner.lock().events.is_empty() {
+            IoEvents::empty()
+        } else {
+            IoEvents::IN
+        }
+    }
+}
+
+impl Drop for InotifyFile {
+    fn drop(&mut self) {
+        let mut watches = WATCHES.lock();
+
+        let inner = self.inner.get_mut();
+        for key in inner.watches.values() {
+            let Some(inode_watches) = watches.get_mut(key) else {
+                continue;
+            };
+            inode_watches.retain(|watch| watch.file.as_ptr() != self.this.as_ptr());
+            if inode_watches.is_empty() {
+                watches.remove(key);
+                NR_WATCHED_INODES.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl Pollable for InotifyFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.pollee
+            .poll_with(mask, poller, || self.check_io_events())
+    }
+}
+
+impl FileLike for InotifyFile {
+    fn read(&self, writer: &mut VmWriter) -> Result<usize> {
+        if self.is_nonblocking.load(Ordering::Relaxed) {
+            self.try_read(writer)
+        } else {
+            self.wait_events(IoEvents::IN, None, || self.try_read(writer))
+        }
+    }
+
+    fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
+        match cmd {
+            IoctlCmd::FIONREAD => {
+                let nr_queued_bytes = self.inner.lock().nr_queued_bytes as i32;
+                current_userspace!().write_val(arg, &nr_queued_bytes)?;
+                Ok(0)
+            }
+            _ => return_errno_with_message!(Errno::EINVAL, "the ioctl command is not supported"),
+        }
+    }
+
+    fn status_flags(&self) -> StatusFlags {
+        if self.is_nonblocking.load(Ordering::Relaxed) {
+            StatusFlags::O_NONBLOCK
+        } else {
+            StatusFlags::empty()
+        }
+    }
+
+    fn set_status_flags(&self, new_flags: StatusFlags) -> Result<()> {
+        self.is_nonblocking.store(
+            new_flags.contains(StatusFlags::O_NONBLOCK),
+            Ordering::Relaxed,
+        );
+        Ok(())
+    }
+
+    fn metadata(&self) -> Metadata {
+        // This is a dummy implementation.
+        // TODO: Add "anonymous inode fs" and link `InotifyFile` to it.
+        let now = RealTimeClock::get().read_time();
+        Metadata {
+            dev: 0,
+            ino: 0,
+            size: 0,
+            blk_size: 0,
+            blocks: 0,
+            atime: now,
+            mtime: now,
+            ctime: now,
+            type_: InodeType::NamedPipe,
+            mode: InodeMode::from_bits_truncate(0o600),
+            nlinks: 1,
+            uid: Uid::new_root(),
+            gid: Gid::new_root(),
+            rdev: 0,
+        }
+    }
+}
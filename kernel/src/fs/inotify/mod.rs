@@ -0,0 +1,297 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The inotify API, which monitors the changes of files.
+//!
+//! An inotify instance ([`InotifyFile`]) watches a set of inodes. Each watch is identified by a
+//! watch descriptor, and selects the events of interest with an [`InotifyMask`]. The watches are
+//! kept in a global table indexed by the inodes, so the VFS reports the changes of an inode with
+//! the `notify_*` functions below, and the events are queued to the instances that watch it.
+//!
+//! An event of a directory entry (e.g., a file is created) is reported to the watchers of the
+//! directory with the name of the entry, while an event of an inode itself (e.g., a file is
+//! modified) is reported to the watchers of both the inode and its parent directory.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man7/inotify.7.html>
+
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+pub use self::file::InotifyFile;
+use crate::{
+    fs::utils::{Inode, InodeType},
+    prelude::*,
+};
+
+mod file;
+
+bitflags! {
+    /// The events and the options of an inotify watch.
+    pub struct InotifyMask: u32 {
+        /// The file is accessed.
+        const IN_ACCESS        = 1 << 0;
+        /// The file is modified.
+        const IN_MODIFY        = 1 << 1;
+        /// The metadata is changed.
+        const IN_ATTRIB        = 1 << 2;
+        /// The file opened for writing is closed.
+        const IN_CLOSE_WRITE   = 1 << 3;
+        /// The file not opened for writing is closed.
+        const IN_CLOSE_NOWRITE = 1 << 4;
+        /// The file is opened.
+        const IN_OPEN          = 1 << 5;
+        /// A file is moved from the watched directory.
+        const IN_MOVED_FROM    = 1 << 6;
+        /// A file is moved into the watched directory.
+        const IN_MOVED_TO      = 1 << 7;
+        /// A file is created in the watched directory.
+        const IN_CREATE        = 1 << 8;
+        /// A file is deleted from the watched directory.
+        const IN_DELETE        = 1 << 9;
+        /// The watched file is deleted.
+        const IN_DELETE_SELF   = 1 << 10;
+        /// The watched file is moved.
+        const IN_MOVE_SELF     = 1 << 11;
+
+        /// The file system is unmounted.
+        const IN_UNMOUNT       = 1 << 13;
+        /// The event queue overflows.
+        const IN_Q_OVERFLOW    = 1 << 14;
+        /// The watch is removed.
+        const IN_IGNORED       = 1 << 15;
+
+        /// Only watches the path if it is a directory.
+        const IN_ONLYDIR       = 1 << 24;
+        /// Does not follow the path if it is a symbolic link.
+        const IN_DONT_FOLLOW   = 1 << 25;
+        /// Does not report the events of the unlinked children.
+        ///
+        /// The unlinked children are not tracked, so this option has no effect.
+        const IN_EXCL_UNLINK   = 1 << 26;
+        /// Fails if the inode is already watched.
+        const IN_MASK_CREATE   = 1 << 28;
+        /// Adds the events to the existing watch instead of replacing them.
+        const IN_MASK_ADD      = 1 << 29;
+        /// The subject of the event is a directory.
+        const IN_ISDIR         = 1 << 30;
+        /// Removes the watch after the first event.
+        const IN_ONESHOT       = 1 << 31;
+
+        const IN_CLOSE         = Self::IN_CLOSE_WRITE.bits | Self::IN_CLOSE_NOWRITE.bits;
+        const IN_MOVE          = Self::IN_MOVED_FROM.bits | Self::IN_MOVED_TO.bits;
+        const IN_ALL_EVENTS    = Self::IN_ACCESS.bits | Self::IN_MODIFY.bits
+                               | Self::IN_ATTRIB.bits | Self::IN_CLOSE.bits
+                               | Self::IN_OPEN.bits | Self::IN_MOVE.bits
+                               | Self::IN_CREATE.bits | Self::IN_DELETE.bits
+                               | Self::IN_DELETE_SELF.bits | Self::IN_MOVE_SELF.bits;
+    }
+}
+
+/// The number of the inodes that are watched.
+///
+/// The notifications are skipped quickly if nothing is watched.
+static NR_WATCHED_INODES: AtomicUsize = AtomicUsize::new(0);
+
+/// The inotify watches, indexed by the watched inodes.
+static WATCHES: Mutex<BTreeMap<InodeKey, Vec<Watch>>> = Mutex::new(BTreeMap::new());
+
+/// The cookie that associates the `IN_MOVED_FROM` and `IN_MOVED_TO` events of a rename.
+static NEXT_COOKIE: AtomicU32 = AtomicU32::new(1);
+
+/// The identity of an inode, i.e., its file system and its inode number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct InodeKey {
+    fs: usize,
+    ino: u64,
+}
+
+impl InodeKey {
+    fn new(inode: &dyn Inode) -> Self {
+        Self {
+            fs: Arc::as_ptr(&inode.fs()) as *const () as usize,
+            ino: inode.ino(),
+        }
+    }
+}
+
+/// A watch of an inotify instance on an inode.
+struct Watch {
+    file: Weak<InotifyFile>,
+    wd: u32,
+    mask: InotifyMask,
+    /// The watched inode, which is kept alive as long as it is watched.
+    _inode: Arc<dyn Inode>,
+}
+
+/// Returns whether any inode is watched.
+///
+/// The VFS may check this before it collects the information for the notifications.
+pub fn is_watching() -> bool {
+    NR_WATCHED_INODES.load(Ordering::Relaxed) != 0
+}
+
+/// Reports that a child is created in the directory.
+pub fn notify_create(dir: &dyn Inode, name: &str, type_: InodeType) {
+    if !is_watching() {
+        return;
+    }
+
+    notify_inode(
+        dir,
+        with_isdir(InotifyMask::IN_CREATE, type_),
+        0,
+        Some(name),
+    );
+}
+
+/// Reports that a child is deleted from the directory.
+///
+/// The `child` is the deleted inode, if it is known.
+pub fn notify_delete(dir: &dyn Inode, name: &str, child: Option<&dyn Inode>) {
+    if !is_watching() {
+        return;
+    }
+
+    // Like Linux, the events of the child itself are reported first.
+    if let Some(child) = child {
+        notify_unlinked(child);
+    }
+    let child_type = child.map_or(InodeType::File, |child| child.type_());
+    notify_inode(
+        dir,
+        with_isdir(InotifyMask::IN_DELETE, child_type),
+        0,
+        Some(name),
+    );
+}
+
+/// Reports that a child is moved from the old directory to the new directory.
+///
+/// The `child` is the moved inode, and the `replaced` is the inode that is replaced by the
+/// moved one, if they are known.
+pub fn notify_move(
+    old_dir: &dyn Inode,
+    old_name: &str,
+    new_dir: &dyn Inode,
+    new_name: &str,
+    child: Option<&dyn Inode>,
+    replaced: Option<&dyn Inode>,
+) {
+    if !is_watching() {
+        return;
+    }
+
+    let child_type = child.map_or(InodeType::File, |child| child.type_());
+    let cookie = NEXT_COOKIE.fetch_add(1, Ordering::Relaxed);
+    notify_inode(
+        old_dir,
+        with_isdir(InotifyMask::IN_MOVED_FROM, child_type),
+        cookie,
+        Some(old_name),
+    );
+    notify_inode(
+        new_dir,
+        with_isdir(InotifyMask::IN_MOVED_TO, child_type),
+        cookie,
+        Some(new_name),
+    );
+    if let Some(child) = child {
+        notify_inode(child, InotifyMask::IN_MOVE_SELF, 0, None);
+    }
+    if let Some(replaced) = replaced {
+        notify_unlinked(replaced);
+    }
+}
+
+/// Reports an event of the inode itself, e.g., `IN_MODIFY` or `IN_OPEN`.
+///
+/// The `parent` is the parent directory and the name of the inode, if the inode is accessed via
+/// a path.
+pub fn notify_event(inode: &dyn Inode, parent: Option<(&dyn Inode, &str)>, mask: InotifyMask) {
+    if !is_watching() {
+        return;
+    }
+
+    let mask = with_isdir(mask, inode.type_());
+    notify_inode(inode, mask, 0, None);
+    if let Some((dir, name)) = parent {
+        notify_inode(dir, mask, 0, Some(name));
+    }
+}
+
+/// Reports that a link of the inode is removed.
+///
+/// If the inode has no links, it is deleted and the watches on it are removed.
+fn notify_unlinked(inode: &dyn Inode) {
+    if inode.type_() != InodeType::Dir && inode.metadata().nlinks > 0 {
+        notify_inode(inode, InotifyMask::IN_ATTRIB, 0, None);
+        return;
+    }
+
+    notify_inode(inode, InotifyMask::IN_DELETE_SELF, 0, None);
+    remove_watches(InodeKey::new(inode));
+}
+
+fn with_isdir(mask: InotifyMask, type_: InodeType) -> InotifyMask {
+    if type_ == InodeType::Dir {
+        mask | InotifyMask::IN_ISDIR
+    } else {
+        mask
+    }
+}
+
+/// Queues an event to the instances that watch the inode.
+fn notify_inode(inode: &dyn Inode, mask: InotifyMask, cookie: u32, name: Option<&str>) {
+    let key = InodeKey::new(inode);
+
+    // The instances are dropped after the lock is released, because dropping an instance
+    // removes its watches.
+    let mut files = Vec::new();
+
+    let mut watches = WATCHES.lock();
+    let Some(inode_watches) = watches.get_mut(&key) else {
+        return;
+    };
+    inode_watches.retain(|watch| {
+        if !watch.mask.intersects(mask & InotifyMask::IN_ALL_EVENTS) {
+            return true;
+        }
+        let Some(file) = watch.file.upgrade() else {
+            return true;
+        };
+
+        file.push_event(watch.wd as i32, mask, cookie, name);
+        let is_oneshot = watch.mask.contains(InotifyMask::IN_ONESHOT);
+        if is_oneshot {
+            file.remove_watch_and_notify(watch.wd);
+        }
+        files.push(file);
+        !is_oneshot
+    });
+    if inode_watches.is_empty() {
+        watches.remove(&key);
+        NR_WATCHED_INODES.fetch_sub(1, Ordering::Relaxed);
+    }
+    drop(watches);
+
+    drop(files);
+}
+
+/// Removes all the watches on the inode.
+fn remove_watches(key: InodeKey) {
+    let mut watches = WATCHES.lock();
+    let Some(inode_watches) = watches.remove(&key) else {
+        return;
+    };
+    NR_WATCHED_INODES.fetch_sub(1, Ordering::Relaxed);
+
+    let files = inode_watches
+        .iter()
+        .filter_map(|watch| {
+            let file = watch.file.upgrade()?;
+            file.remove_watch_and_notify(watch.wd);
+            Some(file)
+        })
+        .collect::<Vec<_>>();
+    drop(watches);
+
+    drop(files);
+}
@@ -0,0 +1,262 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The provided buffers of io_uring.
+//!
+//! Instead of specifying a buffer in each SQE, the user space can provide groups of buffers, from
+//! which the requests with `IOSQE_BUFFER_SELECT` select a buffer only when there are data to
+//! receive. The ID of the selected buffer is reported in the CQE flags. A group is either
+//! - a list of buffers, which are added by `IORING_OP_PROVIDE_BUFFERS` and removed by
+//!   `IORING_OP_REMOVE_BUFFERS`, or
+//! - a ring of buffers in the user space, which is registered by `IORING_REGISTER_PBUF_RING` and
+//!   refilled by the user space without system calls.
+
+use alloc::collections::{btree_map::Entry, BTreeMap, VecDeque};
+use core::sync::atomic::{fence, Ordering};
+
+use aster_rights::Full;
+
+use crate::{prelude::*, vm::vmar::Vmar};
+
+/// The maximum number of buffers in a group.
+const MAX_BIDS_PER_BGID: usize = 1 << 16;
+
+/// The groups of the provided buffers of an io_uring instance.
+pub(super) struct BufferGroups {
+    groups: Mutex<BTreeMap<u16, BufferGroup>>,
+}
+
+enum BufferGroup {
+    List(VecDeque<ProvidedBuffer>),
+    Ring(BufferRing),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ProvidedBuffer {
+    addr: Vaddr,
+    len: usize,
+    bid: u16,
+}
+
+/// A ring of buffers in the user space.
+///
+/// The ring is an array of [`IoUringBuf`], where the tail is stored in the reserved field of the
+/// first entry. The user space adds buffers by advancing the tail, while the kernel consumes them
+/// by advancing the head, which is only known to the kernel.
+struct BufferRing {
+    addr: Vaddr,
+    mask: u16,
+    head: u16,
+}
+
+/// An entry of the buffer rings.
+///
+/// The memory layout is compatible with that of C's `struct io_uring_buf`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct IoUringBuf {
+    addr: u64,
+    len: u32,
+    bid: u16,
+    resv: u16,
+}
+
+/// The offset of the tail in the buffer rings.
+const BUF_RING_TAIL: usize = 14;
+
+/// The registration of a buffer ring.
+///
+/// The memory layout is compatible with that of C's `struct io_uring_buf_reg`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct IoUringBufReg {
+    pub ring_addr: u64,
+    pub ring_entries: u32,
+    pub bgid: u16,
+    pub flags: u16,
+    pub resv: [u64; 3],
+}
+
+impl IoUringBufReg {
+    fn check_reserved(&self) -> Result<()> {
+        if self.flags != 0 || self.resv.iter().any(|resv| *resv != 0) {
+            return_errno_with_message!(Errno::EINVAL, "the reserved fields must be zero");
+        }
+        Ok(())
+    }
+}
+
+impl BufferGroups {
+    pub(super) fn new() -> Self {
+        Self {
+            groups: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Adds `nbufs` contiguous buffers of `len` bytes starting at `addr` to the group.
+    ///
+    /// The buffer IDs start from `bid` and are incremented for each buffer.
+    pub(super) fn provide(
+        &self,
+        bgid: u16,
+        addr: Vaddr,
+        len: usize,
+        nbufs: usize,
+        bid: u16,
+    ) -> Result<()> {
+        if nbufs == 0 || nbufs > u16::MAX as usize {
+            return_errno_with_message!(Errno::E2BIG, "the number of buffers is invalid");
+        }
+        if bid as usize + nbufs >= MAX_BIDS_PER_BGID {
+            return_errno_with_message!(Errno::EINVAL, "the buffer IDs are out of range");
+        }
+        len.checked_mul(nbufs)
+            .and_then(|size| addr.checked_add(size))
+            .ok_or_else(|| Error::with_message(Errno::EOVERFLOW, "the buffers are too large"))?;
+
+        let mut groups = self.groups.lock();
+        let group = groups
+            .entry(bgid)
+            .or_insert_with(|| BufferGroup::List(VecDeque::new()));
+        let BufferGroup::List(bufs) = group else {
+            return_errno_with_message!(Errno::EINVAL, "the group is a buffer ring");
+        };
+
+        bufs.extend((0..nbufs).map(|i| ProvidedBuffer {
+            addr: addr + i * len,
+            len,
+            bid: bid + i as u16,
+        }));
+
+        Ok(())
+    }
+
+    /// Removes at most `nbufs` buffers from the group, returning the number of removed buffers.
+    pub(super) fn remove(&self, bgid: u16, nbufs: usize) -> Result<usize> {
+        if nbufs == 0 || nbufs > u16::MAX as usize {
+            return_errno_with_message!(Errno::EINVAL, "the number of buffers is invalid");
+        }
+
+        let mut groups = self.groups.lock();
+        let Some(group) = groups.get_mut(&bgid) else {
+            return_errno_with_message!(Errno::ENOENT, "the buffer group does not exist");
+        };
+        let BufferGroup::List(bufs) = group else {
+            return_errno_with_message!(Errno::EINVAL, "the group is a buffer ring");
+        };
+
+        let num_removed = nbufs.min(bufs.len());
+        bufs.drain(..num_removed);
+
+        Ok(num_removed)
+    }
+
+    /// Registers a buffer ring as the group.
+    pub(super) fn register_ring(&self, reg: &IoUringBufReg) -> Result<()> {
+        reg.check_reserved()?;
+        if reg.ring_addr == 0 {
+            return_errno_with_message!(Errno::EFAULT, "the ring address is null");
+        }
+        if reg.ring_addr as usize % PAGE_SIZE != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the ring address is not page-aligned");
+        }
+        if !reg.ring_entries.is_power_of_two() || reg.ring_entries > u16::MAX as u32 {
+            return_errno_with_message!(Errno::EINVAL, "the number of ring entries is invalid");
+        }
+
+        let ring = BufferRing {
+            addr: reg.ring_addr as Vaddr,
+            mask: (reg.ring_entries - 1) as u16,
+            head: 0,
+        };
+
+        // Like Linux, a list group can be replaced if it has no buffers.
+        match self.groups.lock().entry(reg.bgid) {
+            Entry::Vacant(entry) => {
+                entry.insert(BufferGroup::Ring(ring));
+            }
+            Entry::Occupied(mut entry) => match entry.get() {
+                BufferGroup::List(bufs) if bufs.is_empty() => {
+                    entry.insert(BufferGroup::Ring(ring));
+                }
+                _ => return_errno_with_message!(Errno::EEXIST, "the buffer group already exists"),
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Unregisters the buffer ring of the group.
+    pub(super) fn unregister_ring(&self, reg: &IoUringBufReg) -> Result<()> {
+        reg.check_reserved()?;
+
+        let mut groups = self.groups.lock();
+        match groups.get(&reg.bgid) {
+            Some(BufferGroup::Ring(_)) => {
+                groups.remove(&reg.bgid);
+                Ok(())
+            }
+            Some(BufferGroup::List(_)) => {
+                return_errno_with_message!(Errno::EINVAL, "the group is not a buffer ring")
+            }
+            None => return_errno_with_message!(Errno::ENOENT, "the buffer group does not exist"),
+        }
+    }
+
+    /// Selects a buffer from the group and calls `op` with its address and length, which is at
+    /// most `max_len` if `max_len` is not zero.
+    ///
+    /// The buffer is consumed only if `op` fills it with some data. Otherwise, it is left in the
+    /// group for the next selection. Returns the result of `op` and the ID of the buffer.
+    ///
+    /// The group is locked during `op`, so `op` should not block for long.
+    pub(super) fn select<F>(
+        &self,
+        bgid: u16,
+        max_len: usize,
+        root_vmar: &Vmar<Full>,
+        op: F,
+    ) -> Result<(usize, u16)>
+    where
+        F: FnOnce(Vaddr, usize) -> Result<usize>,
+    {
+        let clamp = |len: usize| if max_len != 0 { len.min(max_len) } else { len };
+
+        let mut groups = self.groups.lock();
+        let Some(group) = groups.get_mut(&bgid) else {
+            return_errno_with_message!(Errno::ENOBUFS, "the buffer group does not exist");
+        };
+
+        match group {
+            BufferGroup::List(bufs) => {
+                let Some(buf) = bufs.front().copied() else {
+                    return_errno_with_message!(Errno::ENOBUFS, "no buffers are provided");
+                };
+                let len = op(buf.addr, clamp(buf.len))?;
+                if len > 0 {
+                    bufs.pop_front();
+                }
+                Ok((len, buf.bid))
+            }
+            BufferGroup::Ring(ring) => {
+                let mut tail = 0u16;
+                root_vmar.read_bytes(ring.addr + BUF_RING_TAIL, tail.as_bytes_mut())?;
+                // Make sure that the entry is read after the tail.
+                fence(Ordering::Acquire);
+                if tail == ring.head {
+                    return_errno_with_message!(Errno::ENOBUFS, "no buffers are provided");
+                }
+
+                let mut buf = IoUringBuf::new_zeroed();
+                let entry_addr =
+                    ring.addr + (ring.head & ring.mask) as usize * size_of::<IoUringBuf>();
+                root_vmar.read_bytes(entry_addr, buf.as_bytes_mut())?;
+
+                let len = op(buf.addr as Vaddr, clamp(buf.len as usize))?;
+                if len > 0 {
+                    ring.head = ring.head.wrapping_add(1);
+                }
+                Ok((len, buf.bid))
+            }
+        }
+    }
+}
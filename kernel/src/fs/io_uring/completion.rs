@@ -0,0 +1,173 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::collections::VecDeque;
+use core::sync::atomic::{fence, AtomicBool, Ordering};
+
+use ostd::sync::WaitQueue;
+
+use super::ring::{CqeFlags, IoUringCqe, RingLayout, SharedMemory, SqRingFlags};
+use crate::{
+    events::IoEvents,
+    prelude::*,
+    process::signal::{PollHandle, Pollee},
+};
+
+/// The completion queue (CQ) of an io_uring instance.
+///
+/// The CQEs are posted by both the submitting tasks and the worker threads. If the CQ ring is
+/// full, the CQEs are kept in an overflow list and posted when the user space consumes the CQ
+/// ring, so that no CQE is ever dropped.
+pub(super) struct CompletionQueue {
+    rings: Arc<SharedMemory>,
+    layout: RingLayout,
+    inner: Mutex<Inner>,
+    wait_queue: WaitQueue,
+    pollee: Pollee,
+    /// Whether the io_uring instance is closed, after which the requests should stop waiting.
+    is_closed: AtomicBool,
+    /// The pollee that is notified when the io_uring instance is closed.
+    close_pollee: Pollee,
+}
+
+struct Inner {
+    /// The tail of the CQ ring, which is only updated by the kernel.
+    tail: u32,
+    /// The CQEs that cannot be posted because the CQ ring is full.
+    overflow: VecDeque<IoUringCqe>,
+}
+
+impl CompletionQueue {
+    pub(super) fn new(rings: Arc<SharedMemory>, layout: RingLayout) -> Self {
+        Self {
+            rings,
+            layout,
+            inner: Mutex::new(Inner {
+                tail: 0,
+                overflow: VecDeque::new(),
+            }),
+            wait_queue: WaitQueue::new(),
+            pollee: Pollee::new(),
+            is_closed: AtomicBool::new(false),
+            close_pollee: Pollee::new(),
+        }
+    }
+
+    /// Posts the result of a request.
+    ///
+    /// The result is either the non-negative return value or the negative error number.
+    pub(super) fn post(&self, user_data: u64, result: Result<usize>) {
+        self.post_with_flags(user_data, result, CqeFlags::empty().bits());
+    }
+
+    /// Posts the result of a request with the CQE flags.
+    ///
+    /// The flags may contain the ID of the selected buffer (see [`CqeFlags::BUFFER`]).
+    pub(super) fn post_with_flags(&self, user_data: u64, result: Result<usize>, flags: u32) {
+        let res = match result {
+            Ok(ret) => ret as i32,
+            Err(err) => -(err.error() as i32),
+        };
+        let cqe = IoUringCqe {
+            user_data,
+            res,
+            flags,
+        };
+
+        let mut inner = self.inner.lock();
+        self.flush_overflow(&mut inner);
+        if !inner.overflow.is_empty() || !self.try_post(&mut inner, &cqe) {
+            inner.overflow.push_back(cqe);
+            self.rings
+                .write_u32(RingLayout::SQ_FLAGS, SqRingFlags::CQ_OVERFLOW.bits());
+        }
+        drop(inner);
+
+        self.wait_queue.wake_all();
+        self.pollee.notify(IoEvents::IN);
+    }
+
+    /// Waits until there are at least `min_complete` CQEs.
+    pub(super) fn wait(&self, min_complete: u32) -> Result<()> {
+        self.wait_queue.pause_until(|| {
+            let mut inner = self.inner.lock();
+            self.flush_overflow(&mut inner);
+            let num_ready = self.num_posted(&inner) as usize + inner.overflow.len();
+            (num_ready >= min_complete as usize).then_some(())
+        })
+    }
+
+    /// Returns whether there are CQEs that the user space has not consumed.
+    pub(super) fn has_ready(&self) -> bool {
+        let inner = self.inner.lock();
+        self.num_posted(&inner) > 0 || !inner.overflow.is_empty()
+    }
+
+    /// Registers a poller that is notified when new CQEs are posted.
+    pub(super) fn register_poller(&self, poller: &mut PollHandle, mask: IoEvents) {
+        self.pollee.register_poller(poller, mask);
+    }
+
+    /// Marks the io_uring instance as closed.
+    ///
+    /// The pollers that are registered via [`Self::register_close_poller`] are woken up.
+    pub(super) fn close(&self) {
+        self.is_closed.store(true, Ordering::Relaxed);
+        self.close_pollee.notify(IoEvents::HUP);
+    }
+
+    /// Registers a poller that is notified when the io_uring instance is closed.
+    pub(super) fn register_close_poller(&self, poller: &mut PollHandle) {
+        self.close_pollee.register_poller(poller, IoEvents::HUP);
+    }
+
+    /// Returns whether the io_uring instance is closed.
+    pub(super) fn is_closed(&self) -> bool {
+        self.is_closed.load(Ordering::Relaxed)
+    }
+
+    /// Posts the overflowed CQEs if the user space has made room for them.
+    pub(super) fn flush(&self) {
+        let mut inner = self.inner.lock();
+        self.flush_overflow(&mut inner);
+    }
+
+    fn flush_overflow(&self, inner: &mut Inner) {
+        if inner.overflow.is_empty() {
+            return;
+        }
+
+        while let Some(cqe) = inner.overflow.front().copied() {
+            if !self.try_post(inner, &cqe) {
+                return;
+            }
+            inner.overflow.pop_front();
+        }
+
+        self.rings.write_u32(RingLayout::SQ_FLAGS, 0);
+    }
+
+    fn try_post(&self, inner: &mut Inner, cqe: &IoUringCqe) -> bool {
+        if self.num_posted(inner) >= self.layout.cq_entries {
+            return false;
+        }
+
+        self.rings.write_val(self.layout.cqe(inner.tail), cqe);
+        inner.tail = inner.tail.wrapping_add(1);
+
+        // Make sure that the CQE is visible before the new tail.
+        fence(Ordering::Release);
+        self.rings.write_u32(RingLayout::CQ_TAIL, inner.tail);
+
+        true
+    }
+
+    /// Returns the number of CQEs that are in the CQ ring.
+    fn num_posted(&self, inner: &Inner) -> u32 {
+        let head = self.rings.read_u32(RingLayout::CQ_HEAD);
+        // Make sure that the CQEs are consumed before they are overwritten.
+        fence(Ordering::Acquire);
+
+        // The user space may write a bogus head, which should not make the kernel misbehave.
+        inner.tail.wrapping_sub(head).min(self.layout.cq_entries)
+    }
+}
@@ -0,0 +1,243 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{fence, Ordering};
+
+use aster_rights::Rights;
+
+use super::{
+    buffer::{BufferGroups, IoUringBufReg},
+    completion::CompletionQueue,
+    op::Op,
+    ring::{IoUringSqe, RingLayout, SharedMemory},
+    worker::IoWorkers,
+    IoCqringOffsets, IoSqringOffsets, IoUringSetupFlags, IORING_OFF_CQ_RING, IORING_OFF_SQES,
+    IORING_OFF_SQ_RING,
+};
+use crate::{
+    events::IoEvents,
+    fs::{
+        file_handle::FileLike,
+        utils::{InodeMode, IoctlCmd, Metadata},
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+    vm::vmo::Vmo,
+};
+
+/// A file-like object that provides the io_uring API.
+///
+/// The SQ ring and the CQ ring are placed in the same shared memory, while the SQEs are placed
+/// in another shared memory. Both of them can be mapped to the user space via `mmap`.
+pub struct IoUringFile {
+    rings: Arc<SharedMemory>,
+    sqes: SharedMemory,
+    layout: RingLayout,
+    flags: IoUringSetupFlags,
+    /// The head of the SQ ring, which is only updated by the kernel.
+    ///
+    /// The lock also serializes the submissions.
+    sq_head: Mutex<u32>,
+    cq: Arc<CompletionQueue>,
+    buffers: Arc<BufferGroups>,
+    workers: Arc<IoWorkers>,
+}
+
+impl IoUringFile {
+    /// Creates a new io_uring file.
+    ///
+    /// The numbers of entries must be powers of two.
+    pub fn new(sq_entries: u32, cq_entries: u32, flags: IoUringSetupFlags) -> Result<Arc<Self>> {
+        debug_assert!(sq_entries.is_power_of_two() && cq_entries.is_power_of_two());
+
+        let layout = RingLayout {
+            sq_entries,
+            cq_entries,
+        };
+        let rings = Arc::new(SharedMemory::new(layout.size())?);
+        layout.init(&rings);
+        let sqes = SharedMemory::new(sq_entries as usize * size_of::<IoUringSqe>())?;
+        let cq = Arc::new(CompletionQueue::new(rings.clone(), layout));
+
+        Ok(Arc::new(Self {
+            rings,
+            sqes,
+            layout,
+            flags,
+            sq_head: Mutex::new(0),
+            cq: cq.clone(),
+            buffers: Arc::new(BufferGroups::new()),
+            workers: IoWorkers::new(cq),
+        }))
+    }
+
+    /// Returns the offsets of the SQ ring fields.
+    pub fn sq_offsets(&self) -> IoSqringOffsets {
+        self.layout.sq_offsets()
+    }
+
+    /// Returns the offsets of the CQ ring fields.
+    pub fn cq_offsets(&self) -> IoCqringOffsets {
+        self.layout.cq_offsets()
+    }
+
+    /// Submits at most `to_submit` requests from the SQ ring.
+    ///
+    /// Returns the number of SQEs that are consumed. The requests that fail to be prepared are
+    /// completed with errors immediately. Unless `IORING_SETUP_SUBMIT_ALL` is specified, such a
+    /// failure stops the submission.
+    pub fn submit(&self, to_submit: u32, ctx: &Context) -> usize {
+        let mut sq_head = self.sq_head.lock();
+
+        let tail = self.rings.read_u32(RingLayout::SQ_TAIL);
+        // Make sure that the SQEs are read after the tail.
+        fence(Ordering::Acquire);
+
+        let num_pending = tail.wrapping_sub(*sq_head).min(self.layout.sq_entries);
+        let mut num_submitted = 0;
+        for _ in 0..to_submit.min(num_pending) {
+            let sqe_idx = self.rings.read_u32(self.layout.sq_array_elem(*sq_head));
+            *sq_head = sq_head.wrapping_add(1);
+
+            if sqe_idx >= self.layout.sq_entries {
+                let dropped = self.rings.read_u32(RingLayout::SQ_DROPPED);
+                self.rings
+                    .write_u32(RingLayout::SQ_DROPPED, dropped.wrapping_add(1));
+                break;
+            }
+
+            let sqe: IoUringSqe = self
+                .sqes
+                .read_val(sqe_idx as usize * size_of::<IoUringSqe>());
+            num_submitted += 1;
+
+            if self.submit_one(&sqe, ctx).is_err()
+                && !self.flags.contains(IoUringSetupFlags::SUBMIT_ALL)
+            {
+                break;
+            }
+        }
+
+        // Make sure that the SQEs are consumed before the new head.
+        fence(Ordering::Release);
+        self.rings.write_u32(RingLayout::SQ_HEAD, *sq_head);
+
+        num_submitted
+    }
+
+    fn submit_one(&self, sqe: &IoUringSqe, ctx: &Context) -> Result<()> {
+        let user_data = sqe.user_data;
+
+        let op = match Op::prepare(sqe, &self.buffers, ctx) {
+            Ok(op) => op,
+            Err(err) => {
+                let errno = err.error();
+                self.cq.post(user_data, Err(err));
+                return_errno!(errno);
+            }
+        };
+
+        if op.is_completed() {
+            op.execute(user_data, &self.cq);
+            return Ok(());
+        }
+
+        self.workers.submit(op, user_data);
+
+        Ok(())
+    }
+
+    /// Waits until there are at least `min_complete` CQEs.
+    pub fn wait_completions(&self, min_complete: u32) -> Result<()> {
+        self.cq.wait(min_complete)
+    }
+
+    /// Posts the CQEs that are delayed because the CQ ring was full.
+    pub fn flush_completions(&self) {
+        self.cq.flush();
+    }
+
+    /// Registers a ring of provided buffers.
+    pub fn register_buf_ring(&self, reg: &IoUringBufReg) -> Result<()> {
+        self.buffers.register_ring(reg)
+    }
+
+    /// Unregisters a ring of provided buffers.
+    pub fn unregister_buf_ring(&self, reg: &IoUringBufReg) -> Result<()> {
+        self.buffers.unregister_ring(reg)
+    }
+
+    fn has_free_sqes(&self) -> bool {
+        let sq_head = *self.sq_head.lock();
+        let tail = self.rings.read_u32(RingLayout::SQ_TAIL);
+        tail.wrapping_sub(sq_head) < self.layout.sq_entries
+    }
+}
+
+impl Drop for IoUringFile {
+    fn drop(&mut self) {
+        // Stop the requests that are waiting, including the multishot requests, which cannot be
+        // canceled otherwise.
+        self.cq.close();
+        self.workers.close();
+    }
+}
+
+impl Pollable for IoUringFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        // The user space consumes the CQEs without notifying the kernel, so the events cannot be
+        // cached and must be checked every time.
+        if let Some(poller) = poller {
+            self.cq
+                .register_poller(poller, mask | IoEvents::ALWAYS_POLL);
+        }
+
+        let mut events = IoEvents::empty();
+        if self.cq.has_ready() {
+            events |= IoEvents::IN;
+        }
+        if self.has_free_sqes() {
+            events |= IoEvents::OUT;
+        }
+
+        events & mask
+    }
+}
+
+impl FileLike for IoUringFile {
+    fn read(&self, _writer: &mut VmWriter) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "io_uring files do not support read");
+    }
+
+    fn write(&self, _reader: &mut VmReader) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "io_uring files do not support write");
+    }
+
+    fn ioctl(&self, _cmd: IoctlCmd, _arg: usize) -> Result<i32> {
+        return_errno_with_message!(Errno::EINVAL, "io_uring files do not support ioctl");
+    }
+
+    fn metadata(&self) -> Metadata {
+        // This is a dummy implementation.
+        // TODO: Add "anonymous inode fs" and link `IoUringFile` to it.
+        Metadata::new_file(
+            0,
+            InodeMode::from_bits_truncate(0o600),
+            aster_block::BLOCK_SIZE,
+        )
+    }
+
+    fn mmap_vmo(&self, offset: usize, len: usize) -> Result<(Vmo, usize)> {
+        let memory = match offset {
+            IORING_OFF_SQ_RING | IORING_OFF_CQ_RING => self.rings.as_ref(),
+            IORING_OFF_SQES => &self.sqes,
+            _ => return_errno_with_message!(Errno::EINVAL, "the mmap offset is invalid"),
+        };
+
+        let vmo: Vmo<Rights> = memory.vmo().dup()?;
+        if len > vmo.size() {
+            return_errno_with_message!(Errno::EINVAL, "the mmap length is too large");
+        }
+
+        Ok((vmo, 0))
+    }
+}
@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The io_uring interface for asynchronous I/O.
+//!
+//! An io_uring instance consists of a submission queue (SQ) and a completion queue (CQ), both of
+//! which are ring buffers shared with the user space via memory mappings. The user space submits
+//! requests by filling submission queue entries (SQEs) and calling `io_uring_enter`. The requests
+//! are then executed by the worker threads of the instance (see [`worker`]), and their results are posted to the CQ as
+//! completion queue entries (CQEs).
+//!
+//! Only a subset of the Linux features is supported. See [`op`] for the supported operations and
+//! [`buffer`] for the provided buffers.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/io_uring.h>.
+
+use crate::prelude::*;
+
+mod buffer;
+mod completion;
+mod file;
+mod op;
+mod ring;
+mod worker;
+
+pub use buffer::IoUringBufReg;
+pub use file::IoUringFile;
+
+/// The maximum number of SQ entries.
+pub const IORING_MAX_ENTRIES: u32 = 32768;
+/// The maximum number of CQ entries.
+pub const IORING_MAX_CQ_ENTRIES: u32 = 2 * IORING_MAX_ENTRIES;
+
+/// The last opcode that is supported.
+pub const IORING_OP_LAST_SUPPORTED: u8 = op::Opcode::LAST as u8;
+
+/// Returns whether the opcode of io_uring requests is supported.
+pub fn is_opcode_supported(opcode: u8) -> bool {
+    op::Opcode::try_from(opcode).is_ok()
+}
+
+/// The `mmap` offset of the SQ ring.
+pub const IORING_OFF_SQ_RING: usize = 0;
+/// The `mmap` offset of the CQ ring.
+pub const IORING_OFF_CQ_RING: usize = 0x8000000;
+/// The `mmap` offset of the SQE array.
+pub const IORING_OFF_SQES: usize = 0x10000000;
+
+/// The parameters of `io_uring_setup`.
+///
+/// The memory layout is compatible with that of C's `struct io_uring_params`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct IoUringParams {
+    pub sq_entries: u32,
+    pub cq_entries: u32,
+    pub flags: u32,
+    pub sq_thread_cpu: u32,
+    pub sq_thread_idle: u32,
+    pub features: u32,
+    pub wq_fd: u32,
+    pub resv: [u32; 3],
+    pub sq_off: IoSqringOffsets,
+    pub cq_off: IoCqringOffsets,
+}
+
+/// The offsets of the SQ ring fields in the memory mapping.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct IoSqringOffsets {
+    pub head: u32,
+    pub tail: u32,
+    pub ring_mask: u32,
+    pub ring_entries: u32,
+    pub flags: u32,
+    pub dropped: u32,
+    pub array: u32,
+    pub resv1: u32,
+    pub user_addr: u64,
+}
+
+/// The offsets of the CQ ring fields in the memory mapping.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct IoCqringOffsets {
+    pub head: u32,
+    pub tail: u32,
+    pub ring_mask: u32,
+    pub ring_entries: u32,
+    pub overflow: u32,
+    pub cqes: u32,
+    pub flags: u32,
+    pub resv1: u32,
+    pub user_addr: u64,
+}
+
+bitflags! {
+    /// The flags of `io_uring_setup`.
+    ///
+    /// Only the flags that are supported are listed.
+    pub struct IoUringSetupFlags: u32 {
+        /// The number of CQ entries is specified in `cq_entries`.
+        const CQSIZE        = 1 << 3;
+        /// The numbers of entries are clamped to the maximum values.
+        const CLAMP         = 1 << 4;
+        /// Continues to submit the requests even if one of them fails.
+        const SUBMIT_ALL    = 1 << 7;
+        /// A hint that the task does not need to be interrupted for completions.
+        const COOP_TASKRUN  = 1 << 8;
+        /// A hint that only one task submits requests.
+        const SINGLE_ISSUER = 1 << 12;
+    }
+}
+
+bitflags! {
+    /// The features of io_uring reported by `io_uring_setup`.
+    pub struct IoUringFeatures: u32 {
+        /// The SQ ring and the CQ ring can be mapped with a single `mmap`.
+        const SINGLE_MMAP   = 1 << 0;
+        /// The CQEs are never dropped when the CQ ring is full.
+        const NODROP        = 1 << 1;
+        /// The data of the requests are consumed when the requests are submitted.
+        const SUBMIT_STABLE = 1 << 2;
+        /// The offset `-1` means the current file position.
+        const RW_CUR_POS    = 1 << 3;
+    }
+}
+
+bitflags! {
+    /// The flags of `io_uring_enter`.
+    ///
+    /// Only the flags that are supported are listed.
+    pub struct IoUringEnterFlags: u32 {
+        /// Waits for the specified number of completions.
+        const GETEVENTS = 1 << 0;
+        /// Wakes up the SQ polling thread, which does not exist.
+        const SQ_WAKEUP = 1 << 1;
+        /// Waits for the SQ to have free entries, which is always true.
+        const SQ_WAIT   = 1 << 2;
+    }
+}
@@ -0,0 +1,715 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The operations of io_uring requests.
+//!
+//! The supported operations are `IORING_OP_NOP`, `IORING_OP_READV`, `IORING_OP_WRITEV`,
+//! `IORING_OP_FSYNC`, `IORING_OP_ACCEPT`, `IORING_OP_OPENAT`, `IORING_OP_STATX`,
+//! `IORING_OP_READ`, `IORING_OP_WRITE`, `IORING_OP_RECV`, `IORING_OP_SPLICE`,
+//! `IORING_OP_PROVIDE_BUFFERS`, and `IORING_OP_REMOVE_BUFFERS`.
+//!
+//! A request is prepared in the context of the submitting task, where the file descriptors are
+//! resolved and the data to be written are copied from the user space. The request is then
+//! executed by a worker thread of the io_uring instance, which writes to the user space via the
+//! root VMAR. The requests that depend on the submitting task (e.g., resolving paths) and the
+//! requests that never block are completed when they are prepared.
+//!
+//! The worker threads cannot be interrupted by signals, so the requests wait for the files to
+//! become ready before performing the operations, which stops with `ECANCELED` when the io_uring
+//! instance is closed.
+//!
+//! The multishot requests (i.e., `IORING_OP_ACCEPT` with `IORING_ACCEPT_MULTISHOT` and
+//! `IORING_OP_RECV` with `IORING_RECV_MULTISHOT`) post a CQE with `IORING_CQE_F_MORE` for each
+//! result until they fail or the io_uring instance is closed, since they cannot be canceled.
+
+use core::ops::Range;
+
+use aster_rights::Full;
+use ostd::sync::RwArc;
+
+use super::{
+    buffer::BufferGroups,
+    completion::CompletionQueue,
+    ring::{CqeFlags, IoUringSqe, IORING_CQE_BUFFER_SHIFT},
+};
+use crate::{
+    events::IoEvents,
+    fs::{
+        file_handle::FileLike,
+        file_table::{get_file_fast, FdCreationFlags, FileDesc, FileTable},
+        utils::{InodeType, PATH_MAX},
+    },
+    net::socket::{SendRecvFlags, Socket},
+    prelude::*,
+    process::signal::{Pollable, Poller},
+    syscall::{do_openat, do_statx},
+    util::{
+        copy_io_vec_ranges_from_user, net::socket_addr_to_c_bytes_and, MultiRead, VmReaderArray,
+    },
+    vm::vmar::Vmar,
+};
+
+/// The opcodes of io_uring requests.
+///
+/// Only the opcodes that are supported are listed.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, TryFromInt, PartialEq, Eq)]
+pub(super) enum Opcode {
+    Nop = 0,
+    Readv = 1,
+    Writev = 2,
+    Fsync = 3,
+    Accept = 13,
+    OpenAt = 18,
+    Statx = 21,
+    Read = 22,
+    Write = 23,
+    Recv = 27,
+    Splice = 30,
+    ProvideBuffers = 31,
+    RemoveBuffers = 32,
+}
+
+impl Opcode {
+    /// The last opcode that is supported.
+    pub(super) const LAST: Self = Self::RemoveBuffers;
+}
+
+bitflags! {
+    /// The flags of SQEs.
+    ///
+    /// Only the flags that are supported are listed.
+    struct SqeFlags: u8 {
+        /// Always executes the request asynchronously, which is always true.
+        const ASYNC = 1 << 4;
+        /// Selects a buffer from the buffer group specified by `buf_group`.
+        const BUFFER_SELECT = 1 << 5;
+    }
+}
+
+/// The flag of `IORING_OP_FSYNC` to synchronize only the file data.
+const IORING_FSYNC_DATASYNC: u32 = 1;
+
+/// The flag of `IORING_OP_ACCEPT` (in `ioprio`) to accept connections repeatedly.
+const IORING_ACCEPT_MULTISHOT: u16 = 1 << 0;
+
+/// The flag of `IORING_OP_RECV` (in `ioprio`) to wait for data before receiving, which is always
+/// true.
+const IORING_RECVSEND_POLL_FIRST: u16 = 1 << 0;
+/// The flag of `IORING_OP_RECV` (in `ioprio`) to receive data repeatedly.
+const IORING_RECV_MULTISHOT: u16 = 1 << 1;
+
+/// The flags of `IORING_OP_SPLICE` that are only hints (i.e., `SPLICE_F_MOVE`, `SPLICE_F_MORE`,
+/// and `SPLICE_F_GIFT`).
+const SPLICE_F_HINTS: u32 = 0x1 | 0x4 | 0x8;
+
+/// The maximum number of bytes that are spliced at once, which is the default capacity of pipes.
+const MAX_SPLICE_LEN: usize = 65536;
+
+/// A prepared io_uring request.
+pub(super) enum Op {
+    Nop,
+    /// A request that is completed when it is prepared.
+    Completed(Result<usize>),
+    Read {
+        file: Arc<dyn FileLike>,
+        offset: Option<usize>,
+        bufs: Box<[Range<Vaddr>]>,
+        root_vmar: Vmar<Full>,
+    },
+    Write {
+        file: Arc<dyn FileLike>,
+        offset: Option<usize>,
+        data: Vec<u8>,
+    },
+    Fsync {
+        file: Arc<dyn FileLike>,
+        data_only: bool,
+    },
+    Accept {
+        file: Arc<dyn FileLike>,
+        /// The user buffer of the socket address, and the pointer to its maximum length.
+        addr: Option<(Vaddr, Vaddr, i32)>,
+        flags: FdCreationFlags,
+        file_table: RwArc<FileTable>,
+        root_vmar: Vmar<Full>,
+        is_multishot: bool,
+    },
+    Recv {
+        file: Arc<dyn FileLike>,
+        buf: RecvBuf,
+        flags: SendRecvFlags,
+        root_vmar: Vmar<Full>,
+        is_multishot: bool,
+    },
+    Splice {
+        file_in: Arc<dyn FileLike>,
+        offset_in: Option<usize>,
+        file_out: Arc<dyn FileLike>,
+        offset_out: Option<usize>,
+        len: usize,
+    },
+}
+
+/// The buffer of `IORING_OP_RECV`.
+pub(super) enum RecvBuf {
+    User(Range<Vaddr>),
+    /// A buffer that is selected from the group when there are data to receive.
+    Selected {
+        buffers: Arc<BufferGroups>,
+        bgid: u16,
+        /// The maximum length of the data, or zero if the length of the buffer is used.
+        max_len: usize,
+    },
+}
+
+impl Op {
+    /// Prepares the request specified by the SQE.
+    pub(super) fn prepare(
+        sqe: &IoUringSqe,
+        buffers: &Arc<BufferGroups>,
+        ctx: &Context,
+    ) -> Result<Self> {
+        let Ok(opcode) = Opcode::try_from(sqe.opcode) else {
+            return_errno_with_message!(Errno::EINVAL, "the opcode is not supported");
+        };
+        let Some(sqe_flags) = SqeFlags::from_bits(sqe.flags) else {
+            return_errno_with_message!(Errno::EINVAL, "the SQE flags are not supported");
+        };
+        if sqe_flags.contains(SqeFlags::BUFFER_SELECT) && opcode != Opcode::Recv {
+            return_errno_with_message!(Errno::EINVAL, "the opcode cannot select buffers");
+        }
+        // The buffer group ID shares the field with the fixed buffer index, which is not
+        // supported.
+        let has_buf_group = sqe_flags.contains(SqeFlags::BUFFER_SELECT)
+            || matches!(opcode, Opcode::ProvideBuffers | Opcode::RemoveBuffers);
+        if (sqe.buf_index != 0 && !has_buf_group)
+            || (sqe.splice_fd_in != 0 && opcode != Opcode::Splice)
+            || sqe.personality != 0
+            || sqe.addr3 != 0
+        {
+            return_errno_with_message!(Errno::EINVAL, "the SQE fields are not supported");
+        }
+
+        let op = match opcode {
+            Opcode::Nop => Self::Nop,
+            Opcode::Readv | Opcode::Read => {
+                let file = get_file(sqe.fd, ctx)?;
+                let offset = parse_offset(sqe)?;
+                let user_space = ctx.user_space();
+                let bufs: Box<[Range<Vaddr>]> = if opcode == Opcode::Readv {
+                    copy_io_vec_ranges_from_user(&user_space, sqe.addr as _, sqe.len as _)?
+                } else {
+                    let addr = sqe.addr as Vaddr;
+                    // Check that the buffer is in the user space.
+                    user_space.writer(addr, sqe.len as _)?;
+                    Box::new([addr..addr + sqe.len as usize])
+                };
+                Self::Read {
+                    file,
+                    offset,
+                    bufs,
+                    root_vmar: user_space.root_vmar().dup()?,
+                }
+            }
+            Opcode::Writev | Opcode::Write => {
+                let file = get_file(sqe.fd, ctx)?;
+                let offset = parse_offset(sqe)?;
+                let user_space = ctx.user_space();
+                let data = if opcode == Opcode::Writev {
+                    let mut reader_array =
+                        VmReaderArray::from_user_io_vecs(&user_space, sqe.addr as _, sqe.len as _)?;
+                    let mut data = alloc_buf(reader_array.sum_lens())?;
+                    reader_array.read(&mut VmWriter::from(data.as_mut_slice()))?;
+                    data
+                } else {
+                    let mut data = alloc_buf(sqe.len as _)?;
+                    user_space
+                        .read_bytes(sqe.addr as _, &mut VmWriter::from(data.as_mut_slice()))?;
+                    data
+                };
+                Self::Write { file, offset, data }
+            }
+            Opcode::Fsync => {
+                if sqe.addr != 0 {
+                    return_errno_with_message!(Errno::EINVAL, "the address must be zero");
+                }
+                if sqe.op_flags & !IORING_FSYNC_DATASYNC != 0 {
+                    return_errno_with_message!(Errno::EINVAL, "the fsync flags are invalid");
+                }
+                Self::Fsync {
+                    file: get_file(sqe.fd, ctx)?,
+                    data_only: sqe.op_flags & IORING_FSYNC_DATASYNC != 0,
+                }
+            }
+            Opcode::Accept => {
+                if sqe.len != 0 || sqe.ioprio & !IORING_ACCEPT_MULTISHOT != 0 {
+                    return_errno_with_message!(Errno::EINVAL, "the accept flags are not supported");
+                }
+                let flags = FdCreationFlags::from_bits(sqe.op_flags)
+                    .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid flags"))?;
+                let file = get_file(sqe.fd, ctx)?;
+
+                let user_space = ctx.user_space();
+                let addr = if sqe.addr != 0 {
+                    let max_len_ptr = sqe.off as Vaddr;
+                    let max_len = user_space.read_val::<i32>(max_len_ptr)?;
+                    if max_len < 0 {
+                        return_errno_with_message!(
+                            Errno::EINVAL,
+                            "the socket address length cannot be negative"
+                        );
+                    }
+                    Some((sqe.addr as Vaddr, max_len_ptr, max_len))
+                } else {
+                    None
+                };
+
+                Self::Accept {
+                    file,
+                    addr,
+                    flags,
+                    file_table: ctx.thread_local.borrow_file_table().unwrap().clone(),
+                    root_vmar: user_space.root_vmar().dup()?,
+                    is_multishot: sqe.ioprio & IORING_ACCEPT_MULTISHOT != 0,
+                }
+            }
+            Opcode::OpenAt => {
+                let path = ctx.user_space().read_cstring(sqe.addr as Vaddr, PATH_MAX)?;
+                let res = do_openat(
+                    sqe.fd as FileDesc,
+                    &path.to_string_lossy(),
+                    sqe.op_flags,
+                    sqe.len as u16,
+                    ctx,
+                );
+                Self::Completed(res.map(|fd| fd as usize))
+            }
+            Opcode::Statx => {
+                let path = ctx.user_space().read_cstring(sqe.addr as Vaddr, PATH_MAX)?;
+                let res = do_statx(
+                    sqe.fd as FileDesc,
+                    &path,
+                    sqe.op_flags,
+                    sqe.len,
+                    sqe.off as Vaddr,
+                    ctx,
+                );
+                Self::Completed(res.map(|()| 0))
+            }
+            Opcode::Recv => {
+                if sqe.off != 0
+                    || sqe.ioprio & !(IORING_RECVSEND_POLL_FIRST | IORING_RECV_MULTISHOT) != 0
+                {
+                    return_errno_with_message!(Errno::EINVAL, "the recv flags are not supported");
+                }
+                let flags = SendRecvFlags::from_bits(sqe.op_flags as i32)
+                    .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid flags"))?;
+                let is_multishot = sqe.ioprio & IORING_RECV_MULTISHOT != 0;
+                let file = get_file(sqe.fd, ctx)?;
+
+                let user_space = ctx.user_space();
+                let buf = if sqe_flags.contains(SqeFlags::BUFFER_SELECT) {
+                    RecvBuf::Selected {
+                        buffers: buffers.clone(),
+                        bgid: sqe.buf_index,
+                        max_len: sqe.len as usize,
+                    }
+                } else {
+                    let addr = sqe.addr as Vaddr;
+                    // Check that the buffer is in the user space.
+                    user_space.writer(addr, sqe.len as _)?;
+                    RecvBuf::User(addr..addr + sqe.len as usize)
+                };
+
+                if is_multishot
+                    && (matches!(buf, RecvBuf::User(_))
+                        || sqe.len != 0
+                        || flags.contains(SendRecvFlags::MSG_WAITALL))
+                {
+                    return_errno_with_message!(
+                        Errno::EINVAL,
+                        "the multishot recv must select buffers without a length"
+                    );
+                }
+
+                Self::Recv {
+                    file,
+                    buf,
+                    flags,
+                    root_vmar: user_space.root_vmar().dup()?,
+                    is_multishot,
+                }
+            }
+            Opcode::Splice => {
+                if sqe.op_flags & !SPLICE_F_HINTS != 0 {
+                    return_errno_with_message!(Errno::EINVAL, "the splice flags are not supported");
+                }
+                Self::Splice {
+                    file_in: get_file(sqe.splice_fd_in, ctx)?,
+                    offset_in: to_offset(sqe.addr)?,
+                    file_out: get_file(sqe.fd, ctx)?,
+                    offset_out: to_offset(sqe.off)?,
+                    len: sqe.len as usize,
+                }
+            }
+            Opcode::ProvideBuffers => {
+                if sqe.op_flags != 0 {
+                    return_errno_with_message!(Errno::EINVAL, "the SQE fields are not supported");
+                }
+                let bid = u16::try_from(sqe.off)
+                    .map_err(|_| Error::with_message(Errno::E2BIG, "the buffer ID is too large"))?;
+                let res = buffers.provide(
+                    sqe.buf_index,
+                    sqe.addr as Vaddr,
+                    sqe.len as usize,
+                    sqe.fd as u32 as usize,
+                    bid,
+                );
+                Self::Completed(res.map(|()| 0))
+            }
+            Opcode::RemoveBuffers => {
+                if sqe.op_flags != 0 || sqe.addr != 0 || sqe.len != 0 || sqe.off != 0 {
+                    return_errno_with_message!(Errno::EINVAL, "the SQE fields are not supported");
+                }
+                Self::Completed(buffers.remove(sqe.buf_index, sqe.fd as u32 as usize))
+            }
+        };
+
+        Ok(op)
+    }
+
+    /// Returns whether the request has been completed or has no work to do.
+    pub(super) fn is_completed(&self) -> bool {
+        matches!(self, Self::Nop | Self::Completed(_))
+    }
+
+    /// Executes the request, posting its results to the CQ.
+    ///
+    /// This method may block, so it should be called by the worker threads unless the request is
+    /// completed.
+    pub(super) fn execute(self, user_data: u64, cq: &CompletionQueue) {
+        let res = match self {
+            Self::Nop => Ok(0),
+            Self::Completed(res) => res,
+            Self::Read {
+                file,
+                offset,
+                bufs,
+                root_vmar,
+            } => wait_ready(file.as_ref(), IoEvents::IN, cq)
+                .and_then(|()| do_read(file.as_ref(), offset, &bufs, &root_vmar)),
+            Self::Write { file, offset, data } => wait_ready(file.as_ref(), IoEvents::OUT, cq)
+                .and_then(|()| match offset {
+                    Some(offset) => file.write_bytes_at(offset, &data),
+                    None => file.write_bytes(&data),
+                }),
+            Self::Fsync { file, data_only } => do_fsync(file.as_ref(), data_only),
+            Self::Accept {
+                file,
+                addr,
+                flags,
+                file_table,
+                root_vmar,
+                is_multishot,
+            } => {
+                let accept = || {
+                    wait_ready(file.as_ref(), IoEvents::IN, cq).and_then(|()| {
+                        do_accept(file.as_ref(), addr, flags, &file_table, &root_vmar)
+                    })
+                };
+                if !is_multishot {
+                    accept()
+                } else {
+                    loop {
+                        let res = accept();
+                        if res.is_err() || cq.is_closed() {
+                            break res;
+                        }
+                        cq.post_with_flags(user_data, res, CqeFlags::MORE.bits());
+                    }
+                }
+            }
+            Self::Recv {
+                file,
+                buf: RecvBuf::User(buf),
+                flags,
+                root_vmar,
+                ..
+            } => file.as_socket_or_err().and_then(|socket| {
+                wait_ready(file.as_ref(), IoEvents::IN, cq)?;
+                do_recv(socket, buf.start, buf.len(), flags, &root_vmar)
+            }),
+            Self::Recv {
+                file,
+                buf:
+                    RecvBuf::Selected {
+                        buffers,
+                        bgid,
+                        max_len,
+                    },
+                flags,
+                root_vmar,
+                is_multishot,
+            } => loop {
+                let (recv_len, bid) = match recv_selected(
+                    file.as_ref(),
+                    &buffers,
+                    bgid,
+                    max_len,
+                    flags,
+                    &root_vmar,
+                    cq,
+                ) {
+                    Ok(res) => res,
+                    Err(err) => break Err(err),
+                };
+                // No buffer is consumed at the end of the stream.
+                if recv_len == 0 {
+                    break Ok(0);
+                }
+
+                let mut cqe_flags =
+                    CqeFlags::BUFFER.bits() | (bid as u32) << IORING_CQE_BUFFER_SHIFT;
+                let is_last = !is_multishot || cq.is_closed();
+                if !is_last {
+                    cqe_flags |= CqeFlags::MORE.bits();
+                }
+                cq.post_with_flags(user_data, Ok(recv_len), cqe_flags);
+                if is_last {
+                    return;
+                }
+            },
+            Self::Splice {
+                file_in,
+                offset_in,
+                file_out,
+                offset_out,
+                len,
+            } => wait_ready(file_in.as_ref(), IoEvents::IN, cq)
+                .and_then(|()| wait_ready(file_out.as_ref(), IoEvents::OUT, cq))
+                .and_then(|()| {
+                    do_splice(
+                        file_in.as_ref(),
+                        offset_in,
+                        file_out.as_ref(),
+                        offset_out,
+                        len,
+                    )
+                }),
+        };
+
+        cq.post(user_data, res);
+    }
+}
+
+/// Waits until the file has some of the events in `mask`, or fails with `ECANCELED` if the io_uring
+/// instance is closed.
+///
+/// The operation may still block if the events are consumed by others before it is performed, in
+/// which case it cannot be canceled until the events occur again.
+fn wait_ready(file: &dyn FileLike, mask: IoEvents, cq: &CompletionQueue) -> Result<()> {
+    let mask = mask | IoEvents::ALWAYS_POLL;
+
+    let mut poller = Poller::new(None);
+    cq.register_close_poller(poller.as_handle_mut());
+    if cq.is_closed() {
+        return_errno_with_message!(Errno::ECANCELED, "the io_uring instance is closed");
+    }
+    if !file.poll(mask, Some(poller.as_handle_mut())).is_empty() {
+        return Ok(());
+    }
+
+    loop {
+        poller.wait()?;
+
+        if cq.is_closed() {
+            return_errno_with_message!(Errno::ECANCELED, "the io_uring instance is closed");
+        }
+        if !file.poll(mask, None).is_empty() {
+            return Ok(());
+        }
+    }
+}
+
+fn get_file(fd: i32, ctx: &Context) -> Result<Arc<dyn FileLike>> {
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, fd as FileDesc).into_owned();
+    Ok(file)
+}
+
+/// Parses the file offset of the read and write requests.
+///
+/// Returns `None` if the current file position should be used.
+fn parse_offset(sqe: &IoUringSqe) -> Result<Option<usize>> {
+    if sqe.op_flags != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the read/write flags are not supported");
+    }
+
+    to_offset(sqe.off)
+}
+
+/// Converts the file offset in the SQE, where `-1` means the current file position (`None`).
+fn to_offset(off: u64) -> Result<Option<usize>> {
+    match off as i64 {
+        -1 => Ok(None),
+        offset if offset < 0 => {
+            return_errno_with_message!(Errno::EINVAL, "the offset cannot be negative")
+        }
+        offset => Ok(Some(offset as usize)),
+    }
+}
+
+/// Allocates a zeroed buffer of `len` bytes, failing with `ENOMEM` if there are no memory.
+fn alloc_buf(len: usize) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    buf.try_reserve_exact(len)
+        .map_err(|_| Error::with_message(Errno::ENOMEM, "the buffer is too large"))?;
+    buf.resize(len, 0);
+    Ok(buf)
+}
+
+fn do_read(
+    file: &dyn FileLike,
+    offset: Option<usize>,
+    bufs: &[Range<Vaddr>],
+    root_vmar: &Vmar<Full>,
+) -> Result<usize> {
+    let mut total_len = 0;
+
+    for buf in bufs {
+        let res = alloc_buf(buf.len()).and_then(|mut data| {
+            let read_len = match offset {
+                Some(offset) => file.read_bytes_at(offset + total_len, &mut data)?,
+                None => file.read_bytes(&mut data)?,
+            };
+            root_vmar.write_bytes(buf.start, &data[..read_len])?;
+            Ok(read_len)
+        });
+
+        let read_len = match res {
+            Ok(read_len) => read_len,
+            // Report the data that have been read, if any.
+            Err(_) if total_len > 0 => break,
+            Err(err) => return Err(err),
+        };
+        total_len += read_len;
+
+        if read_len < buf.len() {
+            break;
+        }
+    }
+
+    Ok(total_len)
+}
+
+fn do_fsync(file: &dyn FileLike, data_only: bool) -> Result<usize> {
+    let dentry = file.as_inode_or_err()?.dentry();
+    if data_only {
+        dentry.sync_data()?;
+    } else {
+        dentry.sync_all()?;
+    }
+    Ok(0)
+}
+
+fn do_accept(
+    file: &dyn FileLike,
+    addr: Option<(Vaddr, Vaddr, i32)>,
+    flags: FdCreationFlags,
+    file_table: &RwArc<FileTable>,
+    root_vmar: &Vmar<Full>,
+) -> Result<usize> {
+    let socket = file.as_socket_or_err()?;
+    let (connected_socket, socket_addr) = socket.accept()?;
+    let fd_flags = flags.apply_to(connected_socket.as_ref())?;
+
+    if let Some((dest, max_len_ptr, max_len)) = addr {
+        let actual_len = socket_addr_to_c_bytes_and(&socket_addr, |bytes| {
+            let written_len = bytes.len().min(max_len as usize);
+            root_vmar.write_bytes(dest, &bytes[..written_len])?;
+            Ok::<usize, Error>(bytes.len())
+        })?;
+        root_vmar.write_bytes(max_len_ptr, (actual_len as i32).as_bytes())?;
+    }
+
+    let fd = file_table.write().insert(connected_socket, fd_flags)?;
+    Ok(fd as usize)
+}
+
+fn do_recv(
+    socket: &dyn Socket,
+    addr: Vaddr,
+    len: usize,
+    flags: SendRecvFlags,
+    root_vmar: &Vmar<Full>,
+) -> Result<usize> {
+    let mut data = alloc_buf(len)?;
+    let (recv_len, _) = socket.recvmsg(
+        &mut VmWriter::from(data.as_mut_slice()).to_fallible(),
+        flags,
+    )?;
+    root_vmar.write_bytes(addr, &data[..recv_len])?;
+    Ok(recv_len)
+}
+
+/// Receives data into a buffer selected from the group, returning the length of the data and the
+/// buffer ID.
+fn recv_selected(
+    file: &dyn FileLike,
+    buffers: &BufferGroups,
+    bgid: u16,
+    max_len: usize,
+    flags: SendRecvFlags,
+    root_vmar: &Vmar<Full>,
+    cq: &CompletionQueue,
+) -> Result<(usize, u16)> {
+    let socket = file.as_socket_or_err()?;
+
+    // Wait for the data first, so that the buffer is not held by a blocking request.
+    wait_ready(file, IoEvents::IN, cq)?;
+
+    buffers.select(bgid, max_len, root_vmar, |addr, len| {
+        do_recv(socket, addr, len, flags, root_vmar)
+    })
+}
+
+/// Moves data between two files, one of which must be a pipe.
+fn do_splice(
+    file_in: &dyn FileLike,
+    offset_in: Option<usize>,
+    file_out: &dyn FileLike,
+    offset_out: Option<usize>,
+    len: usize,
+) -> Result<usize> {
+    let is_pipe = |file: &dyn FileLike| file.metadata().type_ == InodeType::NamedPipe;
+    if !is_pipe(file_in) && !is_pipe(file_out) {
+        return_errno_with_message!(Errno::EINVAL, "neither of the files is a pipe");
+    }
+    if (is_pipe(file_in) && offset_in.is_some()) || (is_pipe(file_out) && offset_out.is_some()) {
+        return_errno_with_message!(Errno::ESPIPE, "the offset of a pipe must be -1");
+    }
+
+    let mut data = alloc_buf(len.min(MAX_SPLICE_LEN))?;
+    let read_len = match offset_in {
+        Some(offset) => file_in.read_bytes_at(offset, &mut data)?,
+        None => file_in.read_bytes(&mut data)?,
+    };
+
+    // The data that cannot be written are dropped, since they cannot be put back.
+    let mut written_len = 0;
+    while written_len < read_len {
+        let buf = &data[written_len..read_len];
+        let res = match offset_out {
+            Some(offset) => file_out.write_bytes_at(offset + written_len, buf),
+            None => file_out.write_bytes(buf),
+        };
+        match res {
+            Ok(0) => break,
+            Ok(len) => written_len += len,
+            Err(_) if written_len > 0 => break,
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(written_len)
+}
@@ -0,0 +1,208 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use align_ext::AlignExt;
+use aster_rights::Rights;
+use ostd::mm::{UFrame, VmIo};
+
+use super::{IoCqringOffsets, IoSqringOffsets};
+use crate::{
+    prelude::*,
+    vm::vmo::{CommitFlags, Vmo, VmoOptions},
+};
+
+/// Memory that is shared with the user space.
+///
+/// The memory is committed when it is created, so it can be accessed by the kernel without
+/// page faults.
+pub(super) struct SharedMemory {
+    vmo: Vmo<Rights>,
+    frames: Box<[UFrame]>,
+}
+
+impl SharedMemory {
+    /// Allocates zeroed shared memory of at least `size` bytes.
+    pub(super) fn new(size: usize) -> Result<Self> {
+        let size = size.align_up(PAGE_SIZE);
+        let vmo = VmoOptions::<Rights>::new(size).alloc()?;
+        let frames = (0..size / PAGE_SIZE)
+            .map(|page_idx| vmo.commit_on(page_idx, CommitFlags::empty()))
+            .collect::<Result<_>>()?;
+
+        Ok(Self { vmo, frames })
+    }
+
+    /// Returns the VMO that maps the memory.
+    pub(super) fn vmo(&self) -> &Vmo<Rights> {
+        &self.vmo
+    }
+
+    /// Reads a `u32` value with a single memory load.
+    ///
+    /// The values that are concurrently written by the user space must be read via this method.
+    pub(super) fn read_u32(&self, offset: usize) -> u32 {
+        let mut reader = self.frames[offset / PAGE_SIZE].reader();
+        reader.skip(offset % PAGE_SIZE);
+        reader.read_once().unwrap()
+    }
+
+    /// Writes a `u32` value with a single memory store.
+    ///
+    /// The values that are concurrently read by the user space must be written via this method.
+    pub(super) fn write_u32(&self, offset: usize, val: u32) {
+        let mut writer = self.frames[offset / PAGE_SIZE].writer();
+        writer.skip(offset % PAGE_SIZE);
+        writer.write_once(&val).unwrap();
+    }
+
+    /// Reads a value that does not cross the page boundary.
+    pub(super) fn read_val<T: Pod>(&self, offset: usize) -> T {
+        self.frames[offset / PAGE_SIZE]
+            .read_val(offset % PAGE_SIZE)
+            .unwrap()
+    }
+
+    /// Writes a value that does not cross the page boundary.
+    pub(super) fn write_val<T: Pod>(&self, offset: usize, val: &T) {
+        self.frames[offset / PAGE_SIZE]
+            .write_val(offset % PAGE_SIZE, val)
+            .unwrap();
+    }
+}
+
+/// The layout of the memory that contains both the SQ ring and the CQ ring.
+///
+/// The CQEs follow the ring fields, and the SQ array (i.e., the indices of the SQEs) follows
+/// the CQEs. All the offsets are naturally aligned, so no value crosses the page boundary.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct RingLayout {
+    pub(super) sq_entries: u32,
+    pub(super) cq_entries: u32,
+}
+
+impl RingLayout {
+    pub(super) const SQ_HEAD: usize = 0;
+    pub(super) const SQ_TAIL: usize = 4;
+    pub(super) const CQ_HEAD: usize = 8;
+    pub(super) const CQ_TAIL: usize = 12;
+    pub(super) const SQ_RING_MASK: usize = 16;
+    pub(super) const CQ_RING_MASK: usize = 20;
+    pub(super) const SQ_RING_ENTRIES: usize = 24;
+    pub(super) const CQ_RING_ENTRIES: usize = 28;
+    pub(super) const SQ_DROPPED: usize = 32;
+    pub(super) const SQ_FLAGS: usize = 36;
+    pub(super) const CQ_FLAGS: usize = 40;
+    pub(super) const CQ_OVERFLOW: usize = 44;
+    pub(super) const CQES: usize = 64;
+
+    /// Returns the offset of the CQE at `idx`.
+    pub(super) fn cqe(&self, idx: u32) -> usize {
+        Self::CQES + (idx & (self.cq_entries - 1)) as usize * size_of::<IoUringCqe>()
+    }
+
+    /// Returns the offset of the SQ array.
+    pub(super) fn sq_array(&self) -> usize {
+        self.cqe(0) + self.cq_entries as usize * size_of::<IoUringCqe>()
+    }
+
+    /// Returns the offset of the SQ array element at `idx`.
+    pub(super) fn sq_array_elem(&self, idx: u32) -> usize {
+        self.sq_array() + (idx & (self.sq_entries - 1)) as usize * size_of::<u32>()
+    }
+
+    /// Returns the size of the memory.
+    pub(super) fn size(&self) -> usize {
+        self.sq_array() + self.sq_entries as usize * size_of::<u32>()
+    }
+
+    /// Initializes the read-only ring fields in the memory.
+    pub(super) fn init(&self, memory: &SharedMemory) {
+        memory.write_u32(Self::SQ_RING_MASK, self.sq_entries - 1);
+        memory.write_u32(Self::CQ_RING_MASK, self.cq_entries - 1);
+        memory.write_u32(Self::SQ_RING_ENTRIES, self.sq_entries);
+        memory.write_u32(Self::CQ_RING_ENTRIES, self.cq_entries);
+    }
+
+    pub(super) fn sq_offsets(&self) -> IoSqringOffsets {
+        IoSqringOffsets {
+            head: Self::SQ_HEAD as u32,
+            tail: Self::SQ_TAIL as u32,
+            ring_mask: Self::SQ_RING_MASK as u32,
+            ring_entries: Self::SQ_RING_ENTRIES as u32,
+            flags: Self::SQ_FLAGS as u32,
+            dropped: Self::SQ_DROPPED as u32,
+            array: self.sq_array() as u32,
+            resv1: 0,
+            user_addr: 0,
+        }
+    }
+
+    pub(super) fn cq_offsets(&self) -> IoCqringOffsets {
+        IoCqringOffsets {
+            head: Self::CQ_HEAD as u32,
+            tail: Self::CQ_TAIL as u32,
+            ring_mask: Self::CQ_RING_MASK as u32,
+            ring_entries: Self::CQ_RING_ENTRIES as u32,
+            overflow: Self::CQ_OVERFLOW as u32,
+            cqes: Self::CQES as u32,
+            flags: Self::CQ_FLAGS as u32,
+            resv1: 0,
+            user_addr: 0,
+        }
+    }
+}
+
+bitflags! {
+    /// The flags in the SQ ring.
+    pub(super) struct SqRingFlags: u32 {
+        /// The CQ ring is full and some CQEs are waiting to be posted.
+        const CQ_OVERFLOW = 1 << 1;
+    }
+}
+
+bitflags! {
+    /// The flags in CQEs.
+    pub(super) struct CqeFlags: u32 {
+        /// A provided buffer is selected, whose ID is in the upper 16 bits.
+        const BUFFER = 1 << 0;
+        /// The request will post more CQEs.
+        const MORE = 1 << 1;
+    }
+}
+
+/// The shift of the provided buffer ID in the CQE flags.
+pub(super) const IORING_CQE_BUFFER_SHIFT: u32 = 16;
+
+/// A submission queue entry.
+///
+/// The memory layout is compatible with that of C's `struct io_uring_sqe`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct IoUringSqe {
+    pub(super) opcode: u8,
+    pub(super) flags: u8,
+    pub(super) ioprio: u16,
+    pub(super) fd: i32,
+    /// The file offset, or the second address for some operations.
+    pub(super) off: u64,
+    pub(super) addr: u64,
+    pub(super) len: u32,
+    /// The flags specific to the operation.
+    pub(super) op_flags: u32,
+    pub(super) user_data: u64,
+    pub(super) buf_index: u16,
+    pub(super) personality: u16,
+    pub(super) splice_fd_in: i32,
+    pub(super) addr3: u64,
+    _pad: u64,
+}
+
+/// A completion queue entry.
+///
+/// The memory layout is compatible with that of C's `struct io_uring_cqe`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct IoUringCqe {
+    pub(super) user_data: u64,
+    pub(super) res: i32,
+    pub(super) flags: u32,
+}
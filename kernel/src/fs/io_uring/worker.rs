@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The worker threads of io_uring.
+//!
+//! Each io_uring instance has its own pool of worker threads, which are spawned on demand when
+//! the requests cannot be completed when they are prepared. Since the requests may block for a
+//! long time (e.g., waiting for incoming connections), they are not executed by the shared work
+//! queues, where they could starve the work items of other subsystems.
+
+use alloc::collections::VecDeque;
+
+use ostd::sync::WaitQueue;
+
+use super::{completion::CompletionQueue, op::Op};
+use crate::{prelude::*, thread::kernel_thread::ThreadOptions};
+
+/// The maximum number of worker threads of an io_uring instance.
+///
+/// If all the worker threads are busy, the requests are queued until one of them becomes idle.
+const MAX_WORKERS: usize = 64;
+
+/// The pool of worker threads of an io_uring instance.
+pub(super) struct IoWorkers {
+    inner: Mutex<Inner>,
+    wait_queue: WaitQueue,
+    cq: Arc<CompletionQueue>,
+}
+
+struct Inner {
+    /// The requests that are not yet executed.
+    pending: VecDeque<Request>,
+    num_workers: usize,
+    num_idle: usize,
+    /// Whether the io_uring instance is closed, after which the worker threads should exit.
+    is_closed: bool,
+}
+
+struct Request {
+    op: Op,
+    user_data: u64,
+}
+
+impl IoWorkers {
+    pub(super) fn new(cq: Arc<CompletionQueue>) -> Arc<Self> {
+        Arc::new(Self {
+            inner: Mutex::new(Inner {
+                pending: VecDeque::new(),
+                num_workers: 0,
+                num_idle: 0,
+                is_closed: false,
+            }),
+            wait_queue: WaitQueue::new(),
+            cq,
+        })
+    }
+
+    /// Queues a request to be executed by a worker thread.
+    ///
+    /// A new worker thread is spawned if there are not enough idle ones.
+    pub(super) fn submit(self: &Arc<Self>, op: Op, user_data: u64) {
+        let mut inner = self.inner.lock();
+        inner.pending.push_back(Request { op, user_data });
+
+        if inner.pending.len() > inner.num_idle && inner.num_workers < MAX_WORKERS {
+            inner.num_workers += 1;
+            drop(inner);
+
+            let workers = self.clone();
+            ThreadOptions::new(move || workers.run_worker()).spawn();
+        } else {
+            drop(inner);
+            self.wait_queue.wake_one();
+        }
+    }
+
+    /// Cancels the pending requests and stops the worker threads.
+    ///
+    /// The pending requests are completed with `ECANCELED`. The requests that are being executed
+    /// should stop waiting when they see that the CQ is closed (see [`CompletionQueue::close`]).
+    pub(super) fn close(&self) {
+        let pending = {
+            let mut inner = self.inner.lock();
+            inner.is_closed = true;
+            core::mem::take(&mut inner.pending)
+        };
+        self.wait_queue.wake_all();
+
+        for request in pending {
+            self.cq.post(
+                request.user_data,
+                Err(Error::with_message(
+                    Errno::ECANCELED,
+                    "the io_uring instance is closed",
+                )),
+            );
+        }
+    }
+
+    fn run_worker(&self) {
+        while let Some(request) = self.next_request() {
+            request.op.execute(request.user_data, &self.cq);
+        }
+    }
+
+    /// Waits for the next request, or returns `None` if the worker thread should exit.
+    fn next_request(&self) -> Option<Request> {
+        let mut inner = self.inner.lock();
+        loop {
+            if let Some(request) = inner.pending.pop_front() {
+                return Some(request);
+            }
+            if inner.is_closed {
+                inner.num_workers -= 1;
+                return None;
+            }
+
+            inner.num_idle += 1;
+            drop(inner);
+            self.wait_queue.wait_until(|| {
+                let inner = self.inner.lock();
+                (!inner.pending.is_empty() || inner.is_closed).then_some(())
+            });
+            inner = self.inner.lock();
+            inner.num_idle -= 1;
+        }
+    }
+}
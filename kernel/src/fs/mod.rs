@@ -1,5 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
+pub mod cgroupfs;
 pub mod device;
 pub mod devpts;
 pub mod epoll;
@@ -9,7 +10,10 @@ pub mod file_handle;
 pub mod file_table;
 pub mod fs_resolver;
 pub mod inode_handle;
+pub mod inotify;
+pub mod io_uring;
 pub mod named_pipe;
+pub mod nfs;
 pub mod overlayfs;
 pub mod path;
 pub mod pipe;
@@ -18,7 +22,9 @@ pub mod ramfs;
 pub mod rootfs;
 pub mod sysfs;
 pub mod thread_info;
+pub mod tracefs;
 pub mod utils;
+mod writeback;
 
 use aster_block::BlockDevice;
 use aster_virtio::device::block::device::BlockDevice as VirtIoBlockDevice;
@@ -32,39 +38,41 @@ use crate::{
     prelude::*,
 };
 
-fn start_block_device(device_name: &str) -> Result<Arc<dyn BlockDevice>> {
-    if let Some(device) = aster_block::get_device(device_name) {
-        let cloned_device = device.clone();
-        let task_fn = move || {
-            info!("spawn the virt-io-block thread");
-            let virtio_block_device = cloned_device.downcast_ref::<VirtIoBlockDevice>().unwrap();
-            loop {
-                virtio_block_device.handle_requests();
-            }
-        };
-        crate::ThreadOptions::new(task_fn).spawn();
-        Ok(device)
-    } else {
-        return_errno_with_message!(Errno::ENOENT, "Device does not exist")
-    }
+fn start_block_device(device: Arc<dyn BlockDevice>) {
+    let task_fn = move || {
+        info!("spawn the virt-io-block thread");
+        let virtio_block_device = device.downcast_ref::<VirtIoBlockDevice>().unwrap();
+        loop {
+            virtio_block_device.handle_requests();
+        }
+    };
+    crate::ThreadOptions::new(task_fn).spawn();
 }
 
 pub fn lazy_init() {
+    for (_, device) in aster_block::all_devices() {
+        if device.downcast_ref::<VirtIoBlockDevice>().is_some() {
+            start_block_device(device);
+        }
+    }
+
     //The device name is specified in qemu args as --serial={device_name}
     let ext2_device_name = "vext2";
     let exfat_device_name = "vexfat";
 
-    if let Ok(block_device_ext2) = start_block_device(ext2_device_name) {
+    if let Some(block_device_ext2) = aster_block::get_device(ext2_device_name) {
         let ext2_fs = Ext2::open(block_device_ext2).unwrap();
         let target_path = FsPath::try_from("/ext2").unwrap();
         println!("[kernel] Mount Ext2 fs at {:?} ", target_path);
         self::rootfs::mount_fs_at(ext2_fs, &target_path).unwrap();
     }
 
-    if let Ok(block_device_exfat) = start_block_device(exfat_device_name) {
+    if let Some(block_device_exfat) = aster_block::get_device(exfat_device_name) {
         let exfat_fs = ExfatFS::open(block_device_exfat, ExfatMountOptions::default()).unwrap();
         let target_path = FsPath::try_from("/exfat").unwrap();
         println!("[kernel] Mount ExFat fs at {:?} ", target_path);
         self::rootfs::mount_fs_at(exfat_fs, &target_path).unwrap();
     }
+
+    writeback::init();
 }
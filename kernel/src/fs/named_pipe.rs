@@ -1,56 +1,277 @@
 // SPDX-License-Identifier: MPL-2.0
 
+//! Named pipes (FIFOs).
+//!
+//! A named pipe is a pipe that has a name in the file system. It is attached to its inode, so all
+//! the opened files of the inode share the same pipe, no matter which file system the inode
+//! belongs to.
+
+use ostd::sync::WaitQueue;
+
 use super::{
-    file_handle::FileLike,
-    pipe::{self, PipeReader, PipeWriter},
-    utils::{AccessMode, Metadata},
+    inode_handle::FileIo,
+    pipe::{send_sigpipe_to_current, DEFAULT_PIPE_BUF_SIZE},
+    utils::{AccessMode, Channel, Inode, SeekFrom, StatusFlags},
 };
 use crate::{
     events::IoEvents,
     prelude::*,
-    process::signal::{PollHandle, Pollable},
+    process::signal::{Pause, PollHandle, Pollable},
 };
 
+/// Opens the named pipe of the inode.
+///
+/// See [`NamedPipe::open`] for the blocking semantics.
+pub fn open_named_pipe(
+    inode: &Arc<dyn Inode>,
+    access_mode: AccessMode,
+    status_flags: StatusFlags,
+) -> Result<Arc<dyn FileIo>> {
+    let Some(extension) = inode.extension() else {
+        return_errno_with_message!(
+            Errno::EOPNOTSUPP,
+            "the file system does not support named pipes"
+        );
+    };
+
+    let named_pipe = extension.get_or_put_default::<NamedPipe>();
+    named_pipe.open(access_mode, status_flags.contains(StatusFlags::O_NONBLOCK))
+}
+
+/// A named pipe.
+///
+/// The buffer of the pipe is created when the pipe is opened, and discarded when all of its opened
+/// ends are closed.
+#[derive(Default)]
 pub struct NamedPipe {
-    reader: Arc<PipeReader>,
-    writer: Arc<PipeWriter>,
+    state: Mutex<PipeState>,
+    /// The wait queue for the opens that wait for the other side.
+    wait_queue: WaitQueue,
+}
+
+#[derive(Default)]
+struct PipeState {
+    channel: Option<Arc<Channel<u8>>>,
+    num_readers: usize,
+    num_writers: usize,
+    /// The number of times that the pipe has been opened for reading.
+    reader_opens: usize,
+    /// The number of times that the pipe has been opened for writing.
+    writer_opens: usize,
+}
+
+impl PipeState {
+    /// Updates the channel after the number of readers or writers changes.
+    ///
+    /// Reads see the end of the file if there are no writers, and writes fail with `EPIPE` if
+    /// there are no readers. Both are achieved by shutting down the channel.
+    fn update_channel(&mut self) {
+        if self.num_readers == 0 && self.num_writers == 0 {
+            self.channel = None;
+            return;
+        }
+
+        let channel = self
+            .channel
+            .get_or_insert_with(|| Arc::new(Channel::with_capacity(DEFAULT_PIPE_BUF_SIZE)));
+        if self.num_readers > 0 && self.num_writers > 0 {
+            channel.reopen();
+        } else {
+            channel.producer().shutdown();
+        }
+    }
 }
 
 impl NamedPipe {
-    pub fn new() -> Result<Self> {
-        let (reader, writer) = pipe::new_pair()?;
+    /// Opens the named pipe.
+    ///
+    /// Like Linux, opening the pipe for reading blocks until it is opened for writing, and vice
+    /// versa, unless the pipe is opened for both or `is_nonblocking` is true. In the latter case,
+    /// opening the pipe for writing fails with `ENXIO` if it is not opened for reading.
+    pub fn open(
+        self: &Arc<Self>,
+        access_mode: AccessMode,
+        is_nonblocking: bool,
+    ) -> Result<Arc<dyn FileIo>> {
+        let mut state = self.state.lock();
+
+        if matches!(access_mode, AccessMode::O_WRONLY) && is_nonblocking && state.num_readers == 0 {
+            return_errno_with_message!(Errno::ENXIO, "the named pipe is not opened for reading");
+        }
 
-        Ok(Self { reader, writer })
+        if access_mode.is_readable() {
+            state.num_readers += 1;
+            state.reader_opens += 1;
+        }
+        if access_mode.is_writable() {
+            state.num_writers += 1;
+            state.writer_opens += 1;
+        }
+        state.update_channel();
+
+        // If the open fails below, the end will be closed when it is dropped.
+        let pipe_end = Arc::new(NamedPipeEnd {
+            named_pipe: self.clone(),
+            channel: state.channel.clone().unwrap(),
+            access_mode,
+        });
+
+        let should_wait = !is_nonblocking
+            && match access_mode {
+                AccessMode::O_RDONLY => state.num_writers == 0,
+                AccessMode::O_WRONLY => state.num_readers == 0,
+                AccessMode::O_RDWR => false,
+            };
+        let peer_opens = |state: &PipeState| match access_mode {
+            AccessMode::O_RDONLY => state.writer_opens,
+            AccessMode::O_WRONLY | AccessMode::O_RDWR => state.reader_opens,
+        };
+        let old_peer_opens = peer_opens(&state);
+        drop(state);
+
+        self.wait_queue.wake_all();
+
+        if should_wait {
+            // The wait ends once the other side is opened, even if it is closed again.
+            self.wait_queue
+                .pause_until(|| (peer_opens(&self.state.lock()) != old_peer_opens).then_some(()))?;
+        }
+
+        Ok(pipe_end)
     }
 
-    pub fn with_capacity(capacity: usize) -> Result<Self> {
-        let (reader, writer) = pipe::new_pair_with_capacity(capacity)?;
+    fn close(&self, access_mode: AccessMode) {
+        let mut state = self.state.lock();
 
-        Ok(Self { reader, writer })
+        if access_mode.is_readable() {
+            state.num_readers -= 1;
+        }
+        if access_mode.is_writable() {
+            state.num_writers -= 1;
+        }
+        state.update_channel();
     }
 }
 
-impl Pollable for NamedPipe {
-    fn poll(&self, _mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
-        warn!("Named pipe doesn't support poll now, return IoEvents::empty for now.");
-        IoEvents::empty()
+/// An opened end of a named pipe.
+struct NamedPipeEnd {
+    named_pipe: Arc<NamedPipe>,
+    channel: Arc<Channel<u8>>,
+    access_mode: AccessMode,
+}
+
+impl Pollable for NamedPipeEnd {
+    fn poll(&self, mask: IoEvents, mut poller: Option<&mut PollHandle>) -> IoEvents {
+        let mut events = IoEvents::empty();
+        if self.access_mode.is_readable() {
+            events |= self.channel.consumer().poll(mask, poller.as_deref_mut());
+        }
+        if self.access_mode.is_writable() {
+            events |= self.channel.producer().poll(mask, poller);
+        }
+        events
     }
 }
 
-impl FileLike for NamedPipe {
+impl FileIo for NamedPipeEnd {
     fn read(&self, writer: &mut VmWriter) -> Result<usize> {
-        self.reader.read(writer)
+        if !self.access_mode.is_readable() {
+            return_errno_with_message!(Errno::EBADF, "the named pipe is not opened for reading");
+        }
+
+        let consumer = self.channel.consumer();
+        self.wait_events(IoEvents::IN, None, || consumer.try_read(writer))
     }
 
     fn write(&self, reader: &mut VmReader) -> Result<usize> {
-        self.writer.write(reader)
+        if !self.access_mode.is_writable() {
+            return_errno_with_message!(Errno::EBADF, "the named pipe is not opened for writing");
+        }
+
+        let producer = self.channel.producer();
+        let res = self.wait_events(IoEvents::OUT, None, || producer.try_write(reader));
+
+        if res.as_ref().is_err_and(|err| err.error() == Errno::EPIPE) {
+            send_sigpipe_to_current();
+        }
+
+        res
     }
 
-    fn access_mode(&self) -> AccessMode {
-        AccessMode::O_RDWR
+    fn seek(&self, _pos: SeekFrom) -> Option<Result<usize>> {
+        Some(Err(Error::with_message(
+            Errno::ESPIPE,
+            "the named pipe cannot be repositioned",
+        )))
     }
 
-    fn metadata(&self) -> Metadata {
-        self.reader.metadata()
+    fn pipe_capacity(&self) -> Result<usize> {
+        Ok(self.channel.capacity())
+    }
+
+    fn set_pipe_capacity(&self, capacity: usize) -> Result<()> {
+        self.channel.set_capacity(capacity)
+    }
+}
+
+impl Drop for NamedPipeEnd {
+    fn drop(&mut self) {
+        self.named_pipe.close(self.access_mode);
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    fn reader_from(buf: &[u8]) -> VmReader {
+        VmReader::from(buf).to_fallible()
+    }
+
+    fn writer_from(buf: &mut [u8]) -> VmWriter {
+        VmWriter::from(buf).to_fallible()
+    }
+
+    #[ktest]
+    fn test_nonblocking_open() {
+        let named_pipe = Arc::new(NamedPipe::default());
+
+        assert_eq!(
+            named_pipe
+                .open(AccessMode::O_WRONLY, true)
+                .err()
+                .unwrap()
+                .error(),
+            Errno::ENXIO
+        );
+
+        let reader = named_pipe.open(AccessMode::O_RDONLY, true).unwrap();
+        let mut buf = [0; 2];
+        assert_eq!(reader.read(&mut writer_from(&mut buf)).unwrap(), 0);
+
+        let writer = named_pipe.open(AccessMode::O_WRONLY, true).unwrap();
+        assert_eq!(writer.write(&mut reader_from(&[1, 2])).unwrap(), 2);
+        drop(writer);
+
+        // The data written before the writer is closed can still be read.
+        assert_eq!(reader.read(&mut writer_from(&mut buf)).unwrap(), 2);
+        assert_eq!(buf, [1, 2]);
+        assert_eq!(reader.read(&mut writer_from(&mut buf)).unwrap(), 0);
+    }
+
+    #[ktest]
+    fn test_buffer_discarded() {
+        let named_pipe = Arc::new(NamedPipe::default());
+
+        let pipe_end = named_pipe.open(AccessMode::O_RDWR, false).unwrap();
+        assert_eq!(pipe_end.write(&mut reader_from(&[1])).unwrap(), 1);
+        drop(pipe_end);
+        assert!(named_pipe.state.lock().channel.is_none());
+
+        let reader = named_pipe.open(AccessMode::O_RDONLY, true).unwrap();
+        let mut buf = [0; 1];
+        assert_eq!(reader.read(&mut writer_from(&mut buf)).unwrap(), 0);
     }
 }
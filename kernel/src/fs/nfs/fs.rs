@@ -0,0 +1,180 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{
+    inode::NfsInode,
+    options::{AttrTimeouts, NfsMountOptions},
+    proto::{self, Fattr, FileHandle, NfsClient},
+};
+use crate::{
+    fs::utils::{FileSystem, FsFlags, Inode, InodeType, SuperBlock},
+    net::dns,
+    prelude::*,
+};
+
+/// The magic number of NFS.
+const NFS_MAGIC: u64 = 0x6969;
+/// The block size that is reported in `statfs`.
+const BLOCK_SIZE: usize = 4096;
+const MAX_NAME_LEN: usize = 255;
+
+/// An NFSv3 file system, which is mounted from a remote export.
+pub struct NfsFs {
+    client: NfsClient,
+    root: Arc<NfsInode>,
+    rsize: usize,
+    wsize: usize,
+    reg_timeouts: AttrTimeouts,
+    dir_timeouts: AttrTimeouts,
+    /// The inodes that are in use, indexed by their file handles.
+    inodes: Mutex<BTreeMap<FileHandle, Weak<NfsInode>>>,
+    self_: Weak<NfsFs>,
+}
+
+impl NfsFs {
+    /// Mounts a remote export.
+    ///
+    /// The `source` is in the form of `host:/path`, where the host is either a host name or an
+    /// IPv4 address. The `options` are a comma-separated list in the format of nfs(5), e.g.,
+    /// `proto=udp,rsize=8192,actimeo=1`.
+    pub fn mount(source: &str, options: &str) -> Result<Arc<Self>> {
+        let options = NfsMountOptions::parse(options)?;
+
+        let Some((host, path)) = source.split_once(':') else {
+            return_errno_with_message!(Errno::EINVAL, "the NFS source is not host:/path");
+        };
+        if !path.starts_with('/') {
+            return_errno_with_message!(Errno::EINVAL, "the NFS export path is not absolute");
+        }
+        let server = match options.addr {
+            Some(addr) => addr,
+            None => dns::resolve(host)?[0],
+        };
+
+        let root_fh = proto::mount(
+            server,
+            options.mount_port,
+            path,
+            &options.mount_rpc_options(),
+        )?;
+        let client = NfsClient::new(server, options.port, &options.rpc_options())?;
+
+        let fsinfo = client.fsinfo(&root_fh)?;
+        let (rsize, wsize) = options.negotiate_io_sizes(&fsinfo);
+        let root_attr = client.getattr(&root_fh)?;
+        if root_attr.type_ != InodeType::Dir {
+            return_errno_with_message!(Errno::ENOTDIR, "the NFS export is not a directory");
+        }
+
+        Ok(Arc::new_cyclic(|weak_self| {
+            let root = NfsInode::new(
+                root_fh.clone(),
+                root_attr,
+                options.dir_timeouts,
+                weak_self.clone(),
+            );
+            let inodes = BTreeMap::from([(root_fh, Arc::downgrade(&root))]);
+
+            Self {
+                client,
+                root,
+                rsize,
+                wsize,
+                reg_timeouts: options.reg_timeouts,
+                dir_timeouts: options.dir_timeouts,
+                inodes: Mutex::new(inodes),
+                self_: weak_self.clone(),
+            }
+        }))
+    }
+
+    pub(super) fn client(&self) -> &NfsClient {
+        &self.client
+    }
+
+    /// Returns the maximum number of bytes in a READ request.
+    pub(super) fn rsize(&self) -> usize {
+        self.rsize
+    }
+
+    /// Returns the maximum number of bytes in a WRITE request.
+    pub(super) fn wsize(&self) -> usize {
+        self.wsize
+    }
+
+    /// Returns the inode of the file handle, which is created if it is not in use.
+    ///
+    /// The attributes of the inode in use are revalidated with the given ones.
+    pub(super) fn get_inode(&self, fh: FileHandle, attr: Fattr) -> Arc<NfsInode> {
+        let mut inodes = self.inodes.lock();
+        if let Some(inode) = inodes.get(&fh).and_then(Weak::upgrade) {
+            drop(inodes);
+            inode.revalidate(attr);
+            return inode;
+        }
+
+        let timeouts = if attr.type_ == InodeType::Dir {
+            self.dir_timeouts
+        } else {
+            self.reg_timeouts
+        };
+        let inode = NfsInode::new(fh.clone(), attr, timeouts, self.self_.clone());
+        inodes.insert(fh, Arc::downgrade(&inode));
+        inode
+    }
+
+    /// Returns the inode of the file handle if it is in use.
+    pub(super) fn find_inode(&self, fh: &FileHandle) -> Option<Arc<NfsInode>> {
+        self.inodes.lock().get(fh).and_then(Weak::upgrade)
+    }
+
+    /// Removes the inode of the file handle if it is no longer in use.
+    pub(super) fn remove_inode(&self, fh: &FileHandle) {
+        let mut inodes = self.inodes.lock();
+        if inodes
+            .get(fh)
+            .is_some_and(|inode| inode.strong_count() == 0)
+        {
+            inodes.remove(fh);
+        }
+    }
+}
+
+impl FileSystem for NfsFs {
+    fn sync(&self) -> Result<()> {
+        let inodes: Vec<_> = self
+            .inodes
+            .lock()
+            .values()
+            .filter_map(Weak::upgrade)
+            .collect();
+        for inode in inodes {
+            inode.sync_all()?;
+        }
+        Ok(())
+    }
+
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+
+    fn sb(&self) -> SuperBlock {
+        let mut sb = SuperBlock::new(NFS_MAGIC, BLOCK_SIZE, MAX_NAME_LEN);
+
+        match self.client.fsstat(self.root.fh()) {
+            Ok(stat) => {
+                sb.blocks = (stat.tbytes / BLOCK_SIZE as u64) as usize;
+                sb.bfree = (stat.fbytes / BLOCK_SIZE as u64) as usize;
+                sb.bavail = (stat.abytes / BLOCK_SIZE as u64) as usize;
+                sb.files = stat.tfiles as usize;
+                sb.ffree = stat.ffiles as usize;
+            }
+            Err(err) => debug!("failed to fetch the NFS statistics: {:?}", err),
+        }
+
+        sb
+    }
+
+    fn flags(&self) -> FsFlags {
+        FsFlags::empty()
+    }
+}
@@ -0,0 +1,949 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use align_ext::AlignExt;
+use aster_block::bio::BioWaiter;
+use aster_rights::Full;
+
+use super::{
+    fs::NfsFs,
+    options::AttrTimeouts,
+    proto::{Fattr, FileHandle, SetAttrs},
+};
+use crate::{
+    fs::{
+        device::DeviceId,
+        utils::{
+            CachePage, DirentVisitor, FileSystem, Inode, InodeMode, InodeType, Metadata, MknodType,
+            PageCache, PageCacheBackend,
+        },
+    },
+    prelude::*,
+    process::{Gid, Uid},
+    time::clocks::MonotonicCoarseClock,
+    vm::vmo::Vmo,
+};
+
+/// The unit of `st_blocks`.
+const BLOCK_UNIT: u64 = 512;
+
+/// An inode of NFS, which is identified by its file handle.
+pub(super) struct NfsInode {
+    fh: FileHandle,
+    ino: u64,
+    type_: InodeType,
+    attr: Mutex<AttrCache>,
+    timeouts: AttrTimeouts,
+    /// The page cache of a regular file.
+    ///
+    /// The writes go through the page cache to the server synchronously, so the pages are dirty
+    /// only if they are written via memory mappings.
+    page_cache: Option<PageCache>,
+    /// The lock that serializes the writes and the revalidations, both of which may change the
+    /// size of the file.
+    write_lock: Mutex<()>,
+    dir_cache: Mutex<DirCache>,
+    this: Weak<NfsInode>,
+    fs: Weak<NfsFs>,
+}
+
+struct AttrCache {
+    attr: Fattr,
+    timeout: Duration,
+    /// The time when the attributes expire, which is read from the monotonic clock.
+    expires_at: Duration,
+}
+
+/// The cached contents of a directory, which are dropped once the directory is changed.
+#[derive(Default)]
+struct DirCache {
+    /// The file handles of the names that have been looked up.
+    children: BTreeMap<String, FileHandle>,
+    /// The entries that are read from the server.
+    ///
+    /// The entries are read again if the directory is read from the beginning, so that the
+    /// offsets remain consistent while a directory stream is being read.
+    entries: Option<Arc<Vec<CachedDirEntry>>>,
+}
+
+struct CachedDirEntry {
+    name: String,
+    ino: u64,
+    type_: InodeType,
+}
+
+impl NfsInode {
+    pub(super) fn new(
+        fh: FileHandle,
+        attr: Fattr,
+        timeouts: AttrTimeouts,
+        fs: Weak<NfsFs>,
+    ) -> Arc<Self> {
+        Arc::new_cyclic(|weak_self| {
+            let page_cache = (attr.type_ == InodeType::File).then(|| {
+                PageCache::with_capacity(attr.size as usize, weak_self.clone() as _).unwrap()
+            });
+
+            Self {
+                fh,
+                ino: attr.fileid,
+                type_: attr.type_,
+                attr: Mutex::new(AttrCache {
+                    attr,
+                    timeout: timeouts.min,
+                    expires_at: now() + timeouts.min,
+                }),
+                timeouts,
+                page_cache,
+                write_lock: Mutex::new(()),
+                dir_cache: Mutex::new(DirCache::default()),
+                this: weak_self.clone(),
+                fs,
+            }
+        })
+    }
+
+    pub(super) fn fh(&self) -> &FileHandle {
+        &self.fh
+    }
+
+    fn fs_ref(&self) -> Arc<NfsFs> {
+        self.fs.upgrade().unwrap()
+    }
+
+    /// Returns the attributes, which are fetched from the server if the cached ones expire.
+    fn attr(&self) -> Result<Fattr> {
+        {
+            let cache = self.attr.lock();
+            if now() < cache.expires_at {
+                return Ok(cache.attr.clone());
+            }
+        }
+
+        let attr = self.fs_ref().client().getattr(&self.fh)?;
+        self.revalidate(attr.clone());
+        Ok(attr)
+    }
+
+    /// Returns the attributes, or the cached ones if they cannot be fetched.
+    fn attr_or_cached(&self) -> Fattr {
+        self.attr().unwrap_or_else(|err| {
+            debug!("failed to fetch the NFS attributes: {:?}", err);
+            self.attr.lock().attr.clone()
+        })
+    }
+
+    /// Updates the attributes that are fetched from the server.
+    ///
+    /// If the file has been changed by others, the cached data is dropped.
+    pub(super) fn revalidate(&self, attr: Fattr) {
+        let _guard = self.write_lock.lock();
+
+        let is_data_changed = {
+            let mut cache = self.attr.lock();
+            let is_data_changed = cache.attr.mtime != attr.mtime || cache.attr.size != attr.size;
+
+            cache.timeout = if cache.attr == attr {
+                (cache.timeout * 2).clamp(self.timeouts.min, self.timeouts.max)
+            } else {
+                self.timeouts.min
+            };
+            cache.expires_at = now() + cache.timeout;
+            cache.attr = attr;
+
+            is_data_changed
+        };
+
+        if is_data_changed {
+            self.invalidate_data();
+        }
+    }
+
+    /// Updates the attributes that are returned by an operation of this client.
+    ///
+    /// If the server does not return the attributes, the cached ones are expired.
+    fn update_attr(&self, attr: Option<Fattr>) {
+        let mut cache = self.attr.lock();
+        match attr {
+            Some(attr) => {
+                cache.attr = attr;
+                cache.expires_at = now() + cache.timeout;
+            }
+            None => cache.expires_at = Duration::ZERO,
+        }
+    }
+
+    /// Updates the attributes that are returned by a write of this client.
+    ///
+    /// The size is not decreased, since the file may have been extended by a write that is
+    /// still in progress.
+    fn update_attr_after_write(&self, attr: Option<Fattr>) {
+        let mut cache = self.attr.lock();
+        match attr {
+            Some(mut attr) => {
+                attr.size = attr.size.max(cache.attr.size);
+                cache.attr = attr;
+                cache.expires_at = now() + cache.timeout;
+            }
+            None => cache.expires_at = Duration::ZERO,
+        }
+    }
+
+    fn expire_attr(&self) {
+        self.attr.lock().expires_at = Duration::ZERO;
+    }
+
+    fn cached_size(&self) -> usize {
+        self.attr.lock().attr.size as usize
+    }
+
+    /// Drops the cached pages of a file, or the cached contents of a directory.
+    fn invalidate_data(&self) {
+        *self.dir_cache.lock() = DirCache::default();
+
+        let Some(page_cache) = &self.page_cache else {
+            return;
+        };
+        let old_size = page_cache.pages().size();
+        page_cache.discard_range(0..old_size);
+        let res = page_cache
+            .resize(0)
+            .and_then(|_| page_cache.resize(self.cached_size()));
+        if let Err(err) = res {
+            warn!("failed to invalidate the NFS page cache: {:?}", err);
+        }
+    }
+
+    /// Reads the data from the server.
+    ///
+    /// The number of bytes read is less than the length of the buffer only at the end of the file.
+    fn read_remote(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
+        let fs = self.fs_ref();
+
+        let mut read_len = 0;
+        for chunk in buf.chunks_mut(fs.rsize()) {
+            // The post-operation attributes are not used to revalidate the page cache, whose
+            // locks may be held by the caller.
+            let (len, _) = fs
+                .client()
+                .read(&self.fh, (offset + read_len) as u64, chunk)?;
+            read_len += len;
+            if len < chunk.len() {
+                break;
+            }
+        }
+
+        Ok(read_len)
+    }
+
+    /// Writes the data to the server synchronously.
+    fn write_remote(&self, offset: usize, data: &[u8]) -> Result<usize> {
+        let fs = self.fs_ref();
+
+        let mut written_len = 0;
+        while written_len < data.len() {
+            let end = (written_len + fs.wsize()).min(data.len());
+            let (len, attr) = fs.client().write(
+                &self.fh,
+                (offset + written_len) as u64,
+                &data[written_len..end],
+            )?;
+            self.update_attr_after_write(attr);
+            if len == 0 {
+                return_errno_with_message!(Errno::EIO, "the NFS server writes no data");
+            }
+            written_len += len;
+        }
+
+        Ok(written_len)
+    }
+
+    fn setattr(&self, attrs: &SetAttrs) -> Result<()> {
+        let attr = self.fs_ref().client().setattr(&self.fh, attrs)?;
+        self.update_attr(attr);
+        Ok(())
+    }
+
+    /// Caches a file that is created in this directory.
+    fn add_child(&self, name: &str, fh: FileHandle, attr: Fattr) -> Arc<NfsInode> {
+        // The attributes of the directory are changed.
+        self.expire_attr();
+
+        let inode = self.fs_ref().get_inode(fh.clone(), attr);
+        self.dir_cache.lock().children.insert(name.to_string(), fh);
+        inode
+    }
+
+    /// Forgets a file that is removed from this directory.
+    fn forget_child(&self, name: &str) {
+        self.expire_attr();
+
+        let fh = self.dir_cache.lock().children.remove(name);
+        // The number of links of the file is changed.
+        if let Some(fh) = fh
+            && let Some(inode) = self.fs_ref().find_inode(&fh)
+        {
+            inode.expire_attr();
+        }
+    }
+
+    /// Reads the entries of the directory from the server.
+    fn read_entries(&self) -> Result<Arc<Vec<CachedDirEntry>>> {
+        let fs = self.fs_ref();
+        let dir_entries = fs.client().readdir(&self.fh)?;
+
+        let mut children = BTreeMap::new();
+        let mut entries = Vec::with_capacity(dir_entries.len());
+        for entry in dir_entries {
+            let is_dot_or_dotdot = entry.name == "." || entry.name == "..";
+            let type_ = match entry.handle {
+                Some((fh, attr)) => {
+                    let type_ = attr.type_;
+                    // The attributes are fresh, so they are used to revalidate the cached files.
+                    if let Some(inode) = fs.find_inode(&fh) {
+                        inode.revalidate(attr);
+                    }
+                    if !is_dot_or_dotdot {
+                        children.insert(entry.name.clone(), fh);
+                    }
+                    type_
+                }
+                None if is_dot_or_dotdot => InodeType::Dir,
+                None => match fs.client().lookup(&self.fh, &entry.name) {
+                    Ok((fh, attr)) => {
+                        children.insert(entry.name.clone(), fh);
+                        attr.type_
+                    }
+                    // The file has been removed.
+                    Err(_) => continue,
+                },
+            };
+
+            entries.push(CachedDirEntry {
+                name: entry.name,
+                ino: entry.fileid,
+                type_,
+            });
+        }
+
+        let entries = Arc::new(entries);
+        let mut dir_cache = self.dir_cache.lock();
+        dir_cache.children.extend(children);
+        dir_cache.entries = Some(entries.clone());
+        Ok(entries)
+    }
+
+    fn downcast_same_fs<'a>(&self, other: &'a Arc<dyn Inode>) -> Result<&'a NfsInode> {
+        let other = other
+            .downcast_ref::<NfsInode>()
+            .ok_or_else(|| Error::with_message(Errno::EXDEV, "not same fs"))?;
+        if !Weak::ptr_eq(&self.fs, &other.fs) {
+            return_errno_with_message!(Errno::EXDEV, "not same fs");
+        }
+        Ok(other)
+    }
+
+    fn page_cache_ref(&self) -> Result<&PageCache> {
+        match &self.page_cache {
+            Some(page_cache) => Ok(page_cache),
+            None => return_errno!(Errno::EISDIR),
+        }
+    }
+}
+
+impl Drop for NfsInode {
+    fn drop(&mut self) {
+        let Some(fs) = self.fs.upgrade() else {
+            return;
+        };
+        fs.remove_inode(&self.fh);
+    }
+}
+
+impl PageCacheBackend for NfsInode {
+    fn read_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
+        let offset = idx * PAGE_SIZE;
+        let len = self.cached_size().saturating_sub(offset).min(PAGE_SIZE);
+
+        let mut buf = vec![0u8; PAGE_SIZE];
+        self.read_remote(offset, &mut buf[..len])?;
+        frame.writer().write(&mut VmReader::from(buf.as_slice()));
+
+        Ok(BioWaiter::new())
+    }
+
+    fn write_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
+        let offset = idx * PAGE_SIZE;
+        let len = self.cached_size().saturating_sub(offset).min(PAGE_SIZE);
+        if len == 0 {
+            return Ok(BioWaiter::new());
+        }
+
+        let mut buf = vec![0u8; len];
+        frame.reader().read(&mut VmWriter::from(buf.as_mut_slice()));
+        self.write_remote(offset, &buf)?;
+
+        Ok(BioWaiter::new())
+    }
+
+    fn npages(&self) -> usize {
+        self.cached_size().align_up(PAGE_SIZE) / PAGE_SIZE
+    }
+}
+
+impl Inode for NfsInode {
+    fn size(&self) -> usize {
+        self.attr_or_cached().size as usize
+    }
+
+    fn resize(&self, new_size: usize) -> Result<()> {
+        let page_cache = self.page_cache_ref()?;
+        let _guard = self.write_lock.lock();
+
+        self.setattr(&SetAttrs {
+            size: Some(new_size as u64),
+            ..Default::default()
+        })?;
+        page_cache.resize(new_size)
+    }
+
+    fn metadata(&self) -> Metadata {
+        let attr = self.attr_or_cached();
+        Metadata {
+            dev: 0,
+            ino: self.ino,
+            size: attr.size as usize,
+            blk_size: self.fs_ref().wsize(),
+            blocks: attr.used.div_ceil(BLOCK_UNIT) as usize,
+            atime: attr.atime,
+            mtime: attr.mtime,
+            ctime: attr.ctime,
+            type_: self.type_,
+            mode: InodeMode::from_bits_truncate(attr.mode),
+            nlinks: attr.nlink as usize,
+            uid: Uid::new(attr.uid),
+            gid: Gid::new(attr.gid),
+            rdev: attr.rdev,
+        }
+    }
+
+    fn ino(&self) -> u64 {
+        self.ino
+    }
+
+    fn type_(&self) -> InodeType {
+        self.type_
+    }
+
+    fn mode(&self) -> Result<InodeMode> {
+        Ok(InodeMode::from_bits_truncate(self.attr()?.mode))
+    }
+
+    fn set_mode(&self, mode: InodeMode) -> Result<()> {
+        self.setattr(&SetAttrs {
+            mode: Some(mode.bits()),
+            ..Default::default()
+        })
+    }
+
+    fn owner(&self) -> Result<Uid> {
+        Ok(Uid::new(self.attr()?.uid))
+    }
+
+    fn set_owner(&self, uid: Uid) -> Result<()> {
+        self.setattr(&SetAttrs {
+            uid: Some(u32::from(uid)),
+            ..Default::default()
+        })
+    }
+
+    fn group(&self) -> Result<Gid> {
+        Ok(Gid::new(self.attr()?.gid))
+    }
+
+    fn set_group(&self, gid: Gid) -> Result<()> {
+        self.setattr(&SetAttrs {
+            gid: Some(u32::from(gid)),
+            ..Default::default()
+        })
+    }
+
+    fn atime(&self) -> Duration {
+        self.attr_or_cached().atime
+    }
+
+    fn set_atime(&self, time: Duration) {
+        let attrs = SetAttrs {
+            atime: Some(time),
+            ..Default::default()
+        };
+        if let Err(err) = self.setattr(&attrs) {
+            debug!("failed to set the NFS atime: {:?}", err);
+        }
+    }
+
+    fn mtime(&self) -> Duration {
+        self.attr_or_cached().mtime
+    }
+
+    fn set_mtime(&self, time: Duration) {
+        let attrs = SetAttrs {
+            mtime: Some(time),
+            ..Default::default()
+        };
+        if let Err(err) = self.setattr(&attrs) {
+            debug!("failed to set the NFS mtime: {:?}", err);
+        }
+    }
+
+    fn ctime(&self) -> Duration {
+        self.attr_or_cached().ctime
+    }
+
+    fn set_ctime(&self, _time: Duration) {
+        // The ctime is maintained by the server.
+    }
+
+    fn page_cache(&self) -> Option<Vmo<Full>> {
+        self.page_cache
+            .as_ref()
+            .map(|page_cache| page_cache.pages().dup())
+    }
+
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let page_cache = self.page_cache_ref()?;
+        // Revalidate the file so that the changes of others are visible.
+        self.attr()?;
+
+        let (read_off, read_len) = {
+            let file_size = self.cached_size();
+            let start = file_size.min(offset);
+            let end = file_size.min(offset + writer.avail());
+            (start, end - start)
+        };
+        page_cache.pages().read(read_off, writer)?;
+
+        Ok(read_len)
+    }
+
+    fn read_direct_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let page_cache = self.page_cache_ref()?;
+        self.attr()?;
+
+        let (read_off, read_len) = {
+            let file_size = self.cached_size();
+            let start = file_size.min(offset);
+            let end = file_size.min(offset + writer.avail());
+            (start, end - start)
+        };
+        // Write back the changes via memory mappings before reading from the server.
+        page_cache.evict_range(read_off..read_off + read_len)?;
+
+        let mut buf = vec![0u8; read_len];
+        let len = self.read_remote(read_off, &mut buf)?;
+        writer.write_fallible(&mut VmReader::from(&buf[..len]))?;
+
+        Ok(len)
+    }
+
+    fn write_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let page_cache = self.page_cache_ref()?;
+        let _guard = self.write_lock.lock();
+
+        let write_len = reader.remain();
+        let end = offset + write_len;
+        if end > self.cached_size() {
+            page_cache.resize(end)?;
+            self.attr.lock().attr.size = end as u64;
+        }
+
+        let res = page_cache
+            .pages()
+            .write(offset, reader)
+            .and_then(|_| page_cache.evict_range(offset..end));
+        if let Err(err) = res {
+            // Drop the data that may not have been written to the server.
+            self.expire_attr();
+            self.invalidate_data();
+            return Err(err);
+        }
+
+        Ok(write_len)
+    }
+
+    fn write_direct_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let page_cache = self.page_cache_ref()?;
+        let _guard = self.write_lock.lock();
+
+        let mut buf = vec![0u8; reader.remain()];
+        reader.read_fallible(&mut VmWriter::from(buf.as_mut_slice()))?;
+        let end = offset + buf.len();
+
+        // Write back the changes via memory mappings before writing to the server.
+        page_cache.evict_range(offset..end)?;
+        page_cache.discard_range(offset..end);
+
+        let len = self.write_remote(offset, &buf)?;
+        if offset + len > page_cache.pages().size() {
+            page_cache.resize(offset + len)?;
+        }
+
+        Ok(len)
+    }
+
+    fn create(&self, name: &str, type_: InodeType, mode: InodeMode) -> Result<Arc<dyn Inode>> {
+        if self.type_ != InodeType::Dir {
+            return_errno_with_message!(Errno::ENOTDIR, "self is not dir");
+        }
+
+        let fs = self.fs_ref();
+        let client = fs.client();
+        let attrs = SetAttrs {
+            mode: Some(mode.bits()),
+            ..Default::default()
+        };
+        let (fh, attr) = match type_ {
+            InodeType::File => client.create(&self.fh, name, &attrs)?,
+            InodeType::Dir => client.mkdir(&self.fh, name, &attrs)?,
+            InodeType::SymLink => {
+                let dir = self.this.upgrade().unwrap();
+                return Ok(Arc::new(PendingSymlink::new(dir, name, mode)));
+            }
+            InodeType::NamedPipe | InodeType::Socket => {
+                client.mknod(&self.fh, name, type_, &attrs, DeviceId::new(0, 0))?
+            }
+            _ => return_errno_with_message!(Errno::EINVAL, "the file type cannot be created"),
+        };
+
+        Ok(self.add_child(name, fh, attr))
+    }
+
+    fn mknod(&self, name: &str, mode: InodeMode, type_: MknodType) -> Result<Arc<dyn Inode>> {
+        if self.type_ != InodeType::Dir {
+            return_errno_with_message!(Errno::ENOTDIR, "self is not dir");
+        }
+
+        let rdev = match &type_ {
+            MknodType::NamedPipeNode => DeviceId::new(0, 0),
+            MknodType::CharDeviceNode(device) | MknodType::BlockDeviceNode(device) => device.id(),
+        };
+        let attrs = SetAttrs {
+            mode: Some(mode.bits()),
+            ..Default::default()
+        };
+        let (fh, attr) =
+            self.fs_ref()
+                .client()
+                .mknod(&self.fh, name, type_.inode_type(), &attrs, rdev)?;
+
+        Ok(self.add_child(name, fh, attr))
+    }
+
+    fn readdir_at(&self, offset: usize, visitor: &mut dyn DirentVisitor) -> Result<usize> {
+        if self.type_ != InodeType::Dir {
+            return_errno_with_message!(Errno::ENOTDIR, "self is not dir");
+        }
+
+        let cached_entries = if offset == 0 {
+            None
+        } else {
+            self.dir_cache.lock().entries.clone()
+        };
+        let entries = match cached_entries {
+            Some(entries) => entries,
+            None => self.read_entries()?,
+        };
+
+        let try_visit = |idx: &mut usize, visitor: &mut dyn DirentVisitor| -> Result<()> {
+            for (offset, entry) in entries.iter().enumerate().skip(*idx) {
+                visitor.visit(&entry.name, entry.ino, entry.type_, offset)?;
+                *idx = offset + 1;
+            }
+            Ok(())
+        };
+
+        let mut iterate_idx = offset;
+        match try_visit(&mut iterate_idx, visitor) {
+            Err(e) if offset == iterate_idx => Err(e),
+            _ => Ok(iterate_idx - offset),
+        }
+    }
+
+    fn link(&self, old: &Arc<dyn Inode>, name: &str) -> Result<()> {
+        if self.type_ != InodeType::Dir {
+            return_errno_with_message!(Errno::ENOTDIR, "self is not dir");
+        }
+        let old = self.downcast_same_fs(old)?;
+        if old.type_ == InodeType::Dir {
+            return_errno_with_message!(Errno::EPERM, "old is a dir");
+        }
+
+        self.fs_ref().client().link(&old.fh, &self.fh, name)?;
+
+        old.expire_attr();
+        self.expire_attr();
+        self.dir_cache
+            .lock()
+            .children
+            .insert(name.to_string(), old.fh.clone());
+        Ok(())
+    }
+
+    fn unlink(&self, name: &str) -> Result<()> {
+        if self.type_ != InodeType::Dir {
+            return_errno_with_message!(Errno::ENOTDIR, "self is not dir");
+        }
+
+        self.fs_ref().client().remove(&self.fh, name)?;
+        self.forget_child(name);
+        Ok(())
+    }
+
+    fn rmdir(&self, name: &str) -> Result<()> {
+        if self.type_ != InodeType::Dir {
+            return_errno_with_message!(Errno::ENOTDIR, "self is not dir");
+        }
+
+        self.fs_ref().client().rmdir(&self.fh, name)?;
+        self.forget_child(name);
+        Ok(())
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>> {
+        if self.type_ != InodeType::Dir {
+            return_errno_with_message!(Errno::ENOTDIR, "self is not dir");
+        }
+
+        // Revalidate the directory, which drops the cached names if it has been changed.
+        self.attr()?;
+
+        let fs = self.fs_ref();
+        let cached_fh = self.dir_cache.lock().children.get(name).cloned();
+        if let Some(fh) = cached_fh
+            && let Some(inode) = fs.find_inode(&fh)
+        {
+            return Ok(inode);
+        }
+
+        let (fh, attr) = fs.client().lookup(&self.fh, name)?;
+        self.dir_cache
+            .lock()
+            .children
+            .insert(name.to_string(), fh.clone());
+        Ok(fs.get_inode(fh, attr))
+    }
+
+    fn rename(&self, old_name: &str, target: &Arc<dyn Inode>, new_name: &str) -> Result<()> {
+        if self.type_ != InodeType::Dir {
+            return_errno_with_message!(Errno::ENOTDIR, "self is not dir");
+        }
+        let target = self.downcast_same_fs(target)?;
+        if target.type_ != InodeType::Dir {
+            return_errno_with_message!(Errno::ENOTDIR, "target is not dir");
+        }
+
+        self.fs_ref()
+            .client()
+            .rename(&self.fh, old_name, &target.fh, new_name)?;
+
+        let fh = self.dir_cache.lock().children.remove(old_name);
+        self.expire_attr();
+        // The replaced file, if any, is forgotten.
+        target.forget_child(new_name);
+        if let Some(fh) = fh {
+            target
+                .dir_cache
+                .lock()
+                .children
+                .insert(new_name.to_string(), fh);
+        }
+        Ok(())
+    }
+
+    fn read_link(&self) -> Result<String> {
+        if self.type_ != InodeType::SymLink {
+            return_errno_with_message!(Errno::EINVAL, "self is not symlink");
+        }
+
+        self.fs_ref().client().readlink(&self.fh)
+    }
+
+    fn write_link(&self, _target: &str) -> Result<()> {
+        if self.type_ != InodeType::SymLink {
+            return_errno!(Errno::EISDIR);
+        }
+
+        return_errno_with_message!(
+            Errno::EOPNOTSUPP,
+            "the target of an NFS symlink cannot be changed"
+        );
+    }
+
+    fn sync_all(&self) -> Result<()> {
+        self.sync_data()
+    }
+
+    fn sync_data(&self) -> Result<()> {
+        // Only the changes via memory mappings are not written to the server yet.
+        match &self.page_cache {
+            Some(page_cache) => page_cache.evict_range(0..self.cached_size()),
+            None => Ok(()),
+        }
+    }
+
+    fn fs(&self) -> Arc<dyn FileSystem> {
+        self.fs_ref()
+    }
+
+    fn is_dentry_cacheable(&self) -> bool {
+        // The files can be changed by other clients without notice. The names are cached in
+        // `DirCache` instead, which is dropped once the directory is found changed.
+        false
+    }
+}
+
+/// A symlink that is being created.
+///
+/// NFS creates a symlink together with its target, which is not known until
+/// [`Inode::write_link`] is called after [`Inode::create`].
+struct PendingSymlink {
+    dir: Arc<NfsInode>,
+    name: String,
+    mode: InodeMode,
+    created: Mutex<Option<Arc<NfsInode>>>,
+}
+
+impl PendingSymlink {
+    fn new(dir: Arc<NfsInode>, name: &str, mode: InodeMode) -> Self {
+        Self {
+            dir,
+            name: name.to_string(),
+            mode,
+            created: Mutex::new(None),
+        }
+    }
+
+    fn created(&self) -> Result<Arc<NfsInode>> {
+        self.created
+            .lock()
+            .clone()
+            .ok_or_else(|| Error::with_message(Errno::ENOENT, "the symlink has not been created"))
+    }
+}
+
+impl Inode for PendingSymlink {
+    fn size(&self) -> usize {
+        self.created().map_or(0, |inode| inode.size())
+    }
+
+    fn resize(&self, _new_size: usize) -> Result<()> {
+        return_errno!(Errno::EINVAL);
+    }
+
+    fn metadata(&self) -> Metadata {
+        match self.created() {
+            Ok(inode) => inode.metadata(),
+            Err(_) => Metadata::new_symlink(0, self.mode, PAGE_SIZE),
+        }
+    }
+
+    fn ino(&self) -> u64 {
+        self.created().map_or(0, |inode| inode.ino())
+    }
+
+    fn type_(&self) -> InodeType {
+        InodeType::SymLink
+    }
+
+    fn mode(&self) -> Result<InodeMode> {
+        match self.created() {
+            Ok(inode) => inode.mode(),
+            Err(_) => Ok(self.mode),
+        }
+    }
+
+    fn set_mode(&self, mode: InodeMode) -> Result<()> {
+        self.created()?.set_mode(mode)
+    }
+
+    fn owner(&self) -> Result<Uid> {
+        self.created()?.owner()
+    }
+
+    fn set_owner(&self, uid: Uid) -> Result<()> {
+        self.created()?.set_owner(uid)
+    }
+
+    fn group(&self) -> Result<Gid> {
+        self.created()?.group()
+    }
+
+    fn set_group(&self, gid: Gid) -> Result<()> {
+        self.created()?.set_group(gid)
+    }
+
+    fn atime(&self) -> Duration {
+        self.metadata().atime
+    }
+
+    fn set_atime(&self, time: Duration) {
+        if let Ok(inode) = self.created() {
+            inode.set_atime(time);
+        }
+    }
+
+    fn mtime(&self) -> Duration {
+        self.metadata().mtime
+    }
+
+    fn set_mtime(&self, time: Duration) {
+        if let Ok(inode) = self.created() {
+            inode.set_mtime(time);
+        }
+    }
+
+    fn ctime(&self) -> Duration {
+        self.metadata().ctime
+    }
+
+    fn set_ctime(&self, _time: Duration) {}
+
+    fn read_link(&self) -> Result<String> {
+        self.created()?.read_link()
+    }
+
+    fn write_link(&self, target: &str) -> Result<()> {
+        let mut created = self.created.lock();
+        if created.is_some() {
+            return_errno_with_message!(
+                Errno::EOPNOTSUPP,
+                "the target of an NFS symlink cannot be changed"
+            );
+        }
+
+        let attrs = SetAttrs {
+            mode: Some(self.mode.bits()),
+            ..Default::default()
+        };
+        let (fh, attr) =
+            self.dir
+                .fs_ref()
+                .client()
+                .symlink(&self.dir.fh, &self.name, target, &attrs)?;
+        *created = Some(self.dir.add_child(&self.name, fh, attr));
+        Ok(())
+    }
+
+    fn fs(&self) -> Arc<dyn FileSystem> {
+        self.dir.fs()
+    }
+
+    fn is_dentry_cacheable(&self) -> bool {
+        false
+    }
+}
+
+fn now() -> Duration {
+    MonotonicCoarseClock::get().read_time()
+}
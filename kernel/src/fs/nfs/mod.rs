@@ -0,0 +1,22 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A client of the Network File System version 3 (RFC 1813).
+//!
+//! The export is mounted with the MOUNT protocol, and the files are accessed with the NFS
+//! procedures. Both are ONC RPC programs over TCP or UDP, whose ports are queried from the port
+//! mapper on the server.
+//!
+//! The attributes of the files are cached for a while, which is adapted to how often the files
+//! change, see [`NfsMountOptions`]. Once a file is found changed, its cached pages are dropped.
+//! The writes are synchronous, so the data is on the server when `write` returns.
+//!
+//! [`NfsMountOptions`]: options::NfsMountOptions
+
+mod fs;
+mod inode;
+mod options;
+mod proto;
+mod rpc;
+mod xdr;
+
+pub use fs::NfsFs;
@@ -0,0 +1,293 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use aster_bigtcp::wire::Ipv4Address;
+
+use super::{
+    proto::FsInfo,
+    rpc::{RpcOptions, Transport},
+};
+use crate::prelude::*;
+
+/// The granularity of the read size and the write size.
+const IO_SIZE_ALIGN: u32 = 1024;
+const MIN_IO_SIZE: u32 = 1024;
+/// The maximum read size and write size over TCP, which is the same as that of Linux.
+const MAX_TCP_IO_SIZE: u32 = 1024 * 1024;
+/// The maximum read size and write size over UDP, with which a reply fits in a datagram.
+const MAX_UDP_IO_SIZE: u32 = 32 * 1024;
+
+/// The time within which the attributes of a file are trusted without asking the server.
+///
+/// The timeout starts at `min` for a newly cached file, then is doubled each time the attributes
+/// are found unchanged until it reaches `max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct AttrTimeouts {
+    pub(super) min: Duration,
+    pub(super) max: Duration,
+}
+
+impl AttrTimeouts {
+    const fn from_secs(min: u64, max: u64) -> Self {
+        Self {
+            min: Duration::from_secs(min),
+            max: Duration::from_secs(max),
+        }
+    }
+}
+
+/// The options of mounting an NFS export.
+///
+/// The options are parsed from a comma-separated list in the format of nfs(5). The supported
+/// options are `vers`/`nfsvers` (only version 3), `proto`, `tcp`, `udp`, `port`, `mountport`,
+/// `mountproto`, `addr`, `rsize`, `wsize`, `timeo`, `retrans`, `acregmin`, `acregmax`, `acdirmin`,
+/// `acdirmax`, `actimeo`, `noac`, `resvport`, `noresvport`, and `sec` (only `sys`). Other options,
+/// such as `hard` and `nolock`, are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct NfsMountOptions {
+    pub(super) transport: Transport,
+    /// The port of NFS, which is queried from the port mapper if it is not given.
+    pub(super) port: Option<u16>,
+    pub(super) mount_transport: Option<Transport>,
+    /// The port of the MOUNT protocol, which is queried from the port mapper if it is not given.
+    pub(super) mount_port: Option<u16>,
+    /// The address of the server, which overrides the host name in the source.
+    pub(super) addr: Option<Ipv4Address>,
+    pub(super) rsize: Option<u32>,
+    pub(super) wsize: Option<u32>,
+    timeo: Option<Duration>,
+    retrans: Option<u32>,
+    pub(super) reg_timeouts: AttrTimeouts,
+    pub(super) dir_timeouts: AttrTimeouts,
+    use_resvport: bool,
+}
+
+impl Default for NfsMountOptions {
+    fn default() -> Self {
+        Self {
+            transport: Transport::Tcp,
+            port: None,
+            mount_transport: None,
+            mount_port: None,
+            addr: None,
+            rsize: None,
+            wsize: None,
+            timeo: None,
+            retrans: None,
+            reg_timeouts: AttrTimeouts::from_secs(3, 60),
+            dir_timeouts: AttrTimeouts::from_secs(30, 60),
+            use_resvport: true,
+        }
+    }
+}
+
+impl NfsMountOptions {
+    pub(super) fn parse(options: &str) -> Result<Self> {
+        let mut parsed = Self::default();
+
+        for option in options.split(',').filter(|option| !option.is_empty()) {
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (option, None),
+            };
+            parsed.parse_option(name, value)?;
+        }
+
+        for timeouts in [&mut parsed.reg_timeouts, &mut parsed.dir_timeouts] {
+            timeouts.max = timeouts.max.max(timeouts.min);
+        }
+
+        Ok(parsed)
+    }
+
+    fn parse_option(&mut self, name: &str, value: Option<&str>) -> Result<()> {
+        let number = || -> Result<u32> {
+            value.and_then(|value| value.parse().ok()).ok_or_else(|| {
+                Error::with_message(Errno::EINVAL, "the NFS option requires a number")
+            })
+        };
+        let port = || -> Result<Option<u16>> {
+            match u16::try_from(number()?) {
+                // Zero means that the port is queried from the port mapper.
+                Ok(0) => Ok(None),
+                Ok(port) => Ok(Some(port)),
+                Err(_) => return_errno_with_message!(Errno::EINVAL, "the port is invalid"),
+            }
+        };
+        let transport = || match value {
+            Some("tcp") => Ok(Transport::Tcp),
+            Some("udp") => Ok(Transport::Udp),
+            _ => return_errno_with_message!(Errno::EINVAL, "the transport is not supported"),
+        };
+        let secs = || number().map(|secs| Duration::from_secs(secs as u64));
+
+        match name {
+            "vers" | "nfsvers" => {
+                if value != Some("3") {
+                    return_errno_with_message!(
+                        Errno::EPROTONOSUPPORT,
+                        "only NFS version 3 is supported"
+                    );
+                }
+            }
+            "proto" => self.transport = transport()?,
+            "tcp" => self.transport = Transport::Tcp,
+            "udp" => self.transport = Transport::Udp,
+            "port" => self.port = port()?,
+            "mountproto" => self.mount_transport = Some(transport()?),
+            "mountport" => self.mount_port = port()?,
+            "addr" => {
+                let addr = value.and_then(|value| value.parse::<core::net::Ipv4Addr>().ok());
+                let Some(addr) = addr else {
+                    return_errno_with_message!(Errno::EINVAL, "the server address is invalid");
+                };
+                let [a, b, c, d] = addr.octets();
+                self.addr = Some(Ipv4Address::new(a, b, c, d));
+            }
+            "rsize" => self.rsize = Some(number()?),
+            "wsize" => self.wsize = Some(number()?),
+            // The timeout is in tenths of a second.
+            "timeo" => {
+                self.timeo = Some(Duration::from_millis(number()?.max(1) as u64 * 100));
+            }
+            "retrans" => self.retrans = Some(number()?),
+            "acregmin" => self.reg_timeouts.min = secs()?,
+            "acregmax" => self.reg_timeouts.max = secs()?,
+            "acdirmin" => self.dir_timeouts.min = secs()?,
+            "acdirmax" => self.dir_timeouts.max = secs()?,
+            "actimeo" => {
+                let secs = secs()?;
+                self.reg_timeouts = AttrTimeouts {
+                    min: secs,
+                    max: secs,
+                };
+                self.dir_timeouts = self.reg_timeouts;
+            }
+            "noac" => {
+                self.reg_timeouts = AttrTimeouts::from_secs(0, 0);
+                self.dir_timeouts = self.reg_timeouts;
+            }
+            "resvport" => self.use_resvport = true,
+            "noresvport" => self.use_resvport = false,
+            "sec" => {
+                if value != Some("sys") {
+                    return_errno_with_message!(
+                        Errno::EINVAL,
+                        "only the AUTH_SYS security flavor is supported"
+                    );
+                }
+            }
+            _ => debug!("the NFS option is ignored: {}", name),
+        }
+
+        Ok(())
+    }
+
+    /// Returns the options of the RPC client of NFS.
+    pub(super) fn rpc_options(&self) -> RpcOptions {
+        self.rpc_options_with(self.transport)
+    }
+
+    /// Returns the options of the RPC clients of the MOUNT protocol and the port mapper.
+    pub(super) fn mount_rpc_options(&self) -> RpcOptions {
+        self.rpc_options_with(self.mount_transport.unwrap_or(self.transport))
+    }
+
+    fn rpc_options_with(&self, transport: Transport) -> RpcOptions {
+        // The defaults are the same as those of Linux.
+        let (default_timeo, default_retrans) = match transport {
+            Transport::Tcp => (Duration::from_secs(60), 2),
+            Transport::Udp => (Duration::from_millis(1100), 3),
+        };
+
+        RpcOptions {
+            transport,
+            timeout: self.timeo.unwrap_or(default_timeo),
+            retrans: self.retrans.unwrap_or(default_retrans),
+            use_resvport: self.use_resvport,
+        }
+    }
+
+    /// Negotiates the read size and the write size with the limits of the server.
+    pub(super) fn negotiate_io_sizes(&self, fsinfo: &FsInfo) -> (usize, usize) {
+        let limit = match self.transport {
+            Transport::Tcp => MAX_TCP_IO_SIZE,
+            Transport::Udp => MAX_UDP_IO_SIZE,
+        };
+        let negotiate = |requested: Option<u32>, preferred: u32, max: u32| {
+            // Zero means that the server does not tell its limit or preference.
+            let max = if max == 0 { limit } else { max.min(limit) };
+            let size = requested
+                .or((preferred != 0).then_some(preferred))
+                .unwrap_or(max)
+                .min(max);
+            (size / IO_SIZE_ALIGN * IO_SIZE_ALIGN).max(MIN_IO_SIZE) as usize
+        };
+
+        (
+            negotiate(self.rsize, fsinfo.rtpref, fsinfo.rtmax),
+            negotiate(self.wsize, fsinfo.wtpref, fsinfo.wtmax),
+        )
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn parse_mount_options() {
+        let options = NfsMountOptions::parse(
+            "vers=3,proto=udp,port=2049,mountport=0,addr=10.0.2.2,timeo=5,\
+             rsize=10000,acregmin=10,acregmax=5,nolock,hard",
+        )
+        .unwrap();
+        assert_eq!(options.transport, Transport::Udp);
+        assert_eq!(options.port, Some(2049));
+        assert_eq!(options.mount_port, None);
+        assert_eq!(options.addr, Some(Ipv4Address::new(10, 0, 2, 2)));
+        assert_eq!(options.reg_timeouts, AttrTimeouts::from_secs(10, 10));
+        assert_eq!(options.dir_timeouts, AttrTimeouts::from_secs(30, 60));
+
+        let rpc_options = options.rpc_options();
+        assert_eq!(rpc_options.timeout, Duration::from_millis(500));
+        assert_eq!(rpc_options.retrans, 3);
+
+        let fsinfo = FsInfo {
+            rtmax: 65536,
+            rtpref: 65536,
+            wtmax: 16384,
+            wtpref: 8192,
+        };
+        assert_eq!(options.negotiate_io_sizes(&fsinfo), (9216, 8192));
+
+        assert!(NfsMountOptions::parse("vers=4").is_err());
+        assert!(NfsMountOptions::parse("proto=rdma").is_err());
+        assert!(NfsMountOptions::parse("rsize=big").is_err());
+        assert!(NfsMountOptions::parse("sec=krb5").is_err());
+    }
+
+    #[ktest]
+    fn default_mount_options() {
+        let options = NfsMountOptions::parse("").unwrap();
+        assert_eq!(options, NfsMountOptions::default());
+
+        let rpc_options = options.mount_rpc_options();
+        assert_eq!(rpc_options.transport, Transport::Tcp);
+        assert_eq!(rpc_options.timeout, Duration::from_secs(60));
+
+        let fsinfo = FsInfo {
+            rtmax: 0,
+            rtpref: 0,
+            wtmax: 4 * 1024 * 1024,
+            wtpref: 100,
+        };
+        assert_eq!(
+            options.negotiate_io_sizes(&fsinfo),
+            (MAX_TCP_IO_SIZE as usize, MIN_IO_SIZE as usize)
+        );
+    }
+}
@@ -0,0 +1,739 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The procedures of NFSv3 (RFC 1813), the MOUNT protocol (RFC 1813, Appendix I), and the port
+//! mapper (RFC 1833).
+
+use core::time::Duration;
+
+use aster_bigtcp::wire::Ipv4Address;
+
+use super::{
+    rpc::{RpcClient, RpcOptions, RpcReply, Transport},
+    xdr::{XdrDecoder, XdrEncoder},
+};
+use crate::{
+    fs::{device::DeviceId, utils::InodeType},
+    prelude::*,
+};
+
+const PMAP_PROGRAM: u32 = 100000;
+const PMAP_VERSION: u32 = 2;
+const PMAP_PORT: u16 = 111;
+const PMAPPROC_GETPORT: u32 = 3;
+
+const MOUNT_PROGRAM: u32 = 100005;
+const MOUNT_VERSION: u32 = 3;
+const MOUNTPROC3_MNT: u32 = 1;
+const MNTPATHLEN: usize = 1024;
+
+const NFS_PROGRAM: u32 = 100003;
+const NFS_VERSION: u32 = 3;
+/// The well-known port of NFS, which is used if the port mapper does not know the port.
+pub(super) const NFS_PORT: u16 = 2049;
+
+const NFSPROC3_GETATTR: u32 = 1;
+const NFSPROC3_SETATTR: u32 = 2;
+const NFSPROC3_LOOKUP: u32 = 3;
+const NFSPROC3_READLINK: u32 = 5;
+const NFSPROC3_READ: u32 = 6;
+const NFSPROC3_WRITE: u32 = 7;
+const NFSPROC3_CREATE: u32 = 8;
+const NFSPROC3_MKDIR: u32 = 9;
+const NFSPROC3_SYMLINK: u32 = 10;
+const NFSPROC3_MKNOD: u32 = 11;
+const NFSPROC3_REMOVE: u32 = 12;
+const NFSPROC3_RMDIR: u32 = 13;
+const NFSPROC3_RENAME: u32 = 14;
+const NFSPROC3_LINK: u32 = 15;
+const NFSPROC3_READDIRPLUS: u32 = 17;
+const NFSPROC3_FSSTAT: u32 = 18;
+const NFSPROC3_FSINFO: u32 = 19;
+
+const NFS3_FHSIZE: usize = 64;
+const NFS3_COOKIEVERFSIZE: usize = 8;
+const NFS3_WRITEVERFSIZE: usize = 8;
+const MAX_PATH_LEN: usize = 4096;
+const MAX_NAME_LEN: usize = 255;
+
+// The values of `stable_how`.
+const FILE_SYNC: u32 = 2;
+// The values of `createmode3`.
+const GUARDED: u32 = 1;
+// The values of `time_how`.
+const SET_TO_CLIENT_TIME: u32 = 2;
+
+/// The reply size of READDIRPLUS, which is the maximum size of the directory information.
+const READDIR_MAX_COUNT: u32 = 32768;
+
+/// A file handle, which identifies a file on the server.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct FileHandle(Vec<u8>);
+
+impl FileHandle {
+    fn decode(decoder: &mut XdrDecoder) -> Result<Self> {
+        Ok(Self(decoder.get_opaque(NFS3_FHSIZE)?.to_vec()))
+    }
+
+    fn encode(&self, encoder: &mut XdrEncoder) {
+        encoder.put_opaque(&self.0);
+    }
+}
+
+/// The attributes of a file, i.e., `fattr3`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Fattr {
+    pub(super) type_: InodeType,
+    /// The permission bits, the set-user-ID bit, the set-group-ID bit, and the sticky bit.
+    pub(super) mode: u16,
+    pub(super) nlink: u32,
+    pub(super) uid: u32,
+    pub(super) gid: u32,
+    pub(super) size: u64,
+    pub(super) used: u64,
+    pub(super) rdev: u64,
+    pub(super) fileid: u64,
+    pub(super) atime: Duration,
+    pub(super) mtime: Duration,
+    pub(super) ctime: Duration,
+}
+
+impl Fattr {
+    fn decode(decoder: &mut XdrDecoder) -> Result<Self> {
+        let type_ = match decoder.get_u32()? {
+            1 => InodeType::File,
+            2 => InodeType::Dir,
+            3 => InodeType::BlockDevice,
+            4 => InodeType::CharDevice,
+            5 => InodeType::SymLink,
+            6 => InodeType::Socket,
+            7 => InodeType::NamedPipe,
+            _ => return_errno_with_message!(Errno::EBADMSG, "the file type is invalid"),
+        };
+        let mode = (decoder.get_u32()? & 0o7777) as u16;
+        let nlink = decoder.get_u32()?;
+        let uid = decoder.get_u32()?;
+        let gid = decoder.get_u32()?;
+        let size = decoder.get_u64()?;
+        let used = decoder.get_u64()?;
+        let major = decoder.get_u32()?;
+        let minor = decoder.get_u32()?;
+        // The file system ID
+        decoder.get_u64()?;
+        let fileid = decoder.get_u64()?;
+        let atime = decode_time(decoder)?;
+        let mtime = decode_time(decoder)?;
+        let ctime = decode_time(decoder)?;
+
+        Ok(Self {
+            type_,
+            mode,
+            nlink,
+            uid,
+            gid,
+            size,
+            used,
+            rdev: DeviceId::new(major, minor).into(),
+            fileid,
+            atime,
+            mtime,
+            ctime,
+        })
+    }
+
+    /// Decodes `post_op_attr`, which may not contain the attributes.
+    fn decode_post_op(decoder: &mut XdrDecoder) -> Result<Option<Self>> {
+        if decoder.get_bool()? {
+            Self::decode(decoder).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Decodes `wcc_data` and returns the attributes after the operation.
+    fn decode_wcc(decoder: &mut XdrDecoder) -> Result<Option<Self>> {
+        // The `pre_op_attr` with the size, the mtime, and the ctime
+        if decoder.get_bool()? {
+            decoder.skip(8 + 8 + 8)?;
+        }
+        Self::decode_post_op(decoder)
+    }
+}
+
+fn decode_time(decoder: &mut XdrDecoder) -> Result<Duration> {
+    let secs = decoder.get_u32()?;
+    let nsecs = decoder.get_u32()?;
+    Ok(Duration::new(secs as u64, nsecs.min(999_999_999)))
+}
+
+/// The attributes to set, i.e., `sattr3`.
+#[derive(Debug, Default)]
+pub(super) struct SetAttrs {
+    pub(super) mode: Option<u16>,
+    pub(super) uid: Option<u32>,
+    pub(super) gid: Option<u32>,
+    pub(super) size: Option<u64>,
+    pub(super) atime: Option<Duration>,
+    pub(super) mtime: Option<Duration>,
+}
+
+impl SetAttrs {
+    fn encode(&self, encoder: &mut XdrEncoder) {
+        let mut put_u32 = |value: Option<u32>| match value {
+            Some(value) => encoder.put_bool(true).put_u32(value),
+            None => encoder.put_bool(false),
+        };
+        put_u32(self.mode.map(u32::from));
+        put_u32(self.uid);
+        put_u32(self.gid);
+
+        match self.size {
+            Some(size) => encoder.put_bool(true).put_u64(size),
+            None => encoder.put_bool(false),
+        };
+
+        for time in [self.atime, self.mtime] {
+            match time {
+                Some(time) => encoder
+                    .put_u32(SET_TO_CLIENT_TIME)
+                    .put_u32(time.as_secs() as u32)
+                    .put_u32(time.subsec_nanos()),
+                // `DONT_CHANGE`
+                None => encoder.put_u32(0),
+            };
+        }
+    }
+}
+
+/// The static information of a file system, i.e., the results of FSINFO.
+#[derive(Debug)]
+pub(super) struct FsInfo {
+    pub(super) rtmax: u32,
+    pub(super) rtpref: u32,
+    pub(super) wtmax: u32,
+    pub(super) wtpref: u32,
+}
+
+/// The dynamic information of a file system, i.e., the results of FSSTAT.
+#[derive(Debug)]
+pub(super) struct FsStat {
+    pub(super) tbytes: u64,
+    pub(super) fbytes: u64,
+    pub(super) abytes: u64,
+    pub(super) tfiles: u64,
+    pub(super) ffiles: u64,
+}
+
+/// An entry of a directory, which is read by READDIRPLUS.
+#[derive(Debug)]
+pub(super) struct DirEntry {
+    pub(super) name: String,
+    pub(super) fileid: u64,
+    pub(super) handle: Option<(FileHandle, Fattr)>,
+}
+
+/// Queries the port mapper for the port of the program.
+fn get_port(server: Ipv4Address, program: u32, version: u32, options: &RpcOptions) -> Result<u16> {
+    let pmap = RpcClient::new(
+        server,
+        PMAP_PORT,
+        PMAP_PROGRAM,
+        PMAP_VERSION,
+        options.clone(),
+    )?;
+
+    let mut args = XdrEncoder::new();
+    args.put_u32(program)
+        .put_u32(version)
+        .put_u32(options.transport.ip_protocol())
+        .put_u32(0);
+    let reply = pmap.call(PMAPPROC_GETPORT, &args)?;
+
+    match reply.results().get_u32()? {
+        0 => {
+            return_errno_with_message!(Errno::EPROTONOSUPPORT, "the RPC program is not registered")
+        }
+        port => u16::try_from(port)
+            .map_err(|_| Error::with_message(Errno::EBADMSG, "the port is invalid")),
+    }
+}
+
+/// Mounts the exported directory with the MOUNT protocol and returns its file handle.
+pub(super) fn mount(
+    server: Ipv4Address,
+    port: Option<u16>,
+    path: &str,
+    options: &RpcOptions,
+) -> Result<FileHandle> {
+    if path.len() > MNTPATHLEN {
+        return_errno_with_message!(Errno::ENAMETOOLONG, "the export path is too long");
+    }
+
+    let port = match port {
+        Some(port) => port,
+        None => get_port(server, MOUNT_PROGRAM, MOUNT_VERSION, options)?,
+    };
+    let client = RpcClient::new(server, port, MOUNT_PROGRAM, MOUNT_VERSION, options.clone())?;
+
+    let mut args = XdrEncoder::new();
+    args.put_str(path);
+    let reply = client.call(MOUNTPROC3_MNT, &args)?;
+
+    // The values of `mountstat3` are the same as those of `nfsstat3`.
+    let mut results = reply.results();
+    check_status(&mut results)?;
+    FileHandle::decode(&mut results)
+    // TODO: Check the authentication flavors that follow the file handle.
+}
+
+/// An NFSv3 client.
+pub(super) struct NfsClient {
+    rpc: RpcClient,
+}
+
+impl NfsClient {
+    pub(super) fn new(
+        server: Ipv4Address,
+        port: Option<u16>,
+        options: &RpcOptions,
+    ) -> Result<Self> {
+        let port = match port {
+            Some(port) => port,
+            None => match get_port(server, NFS_PROGRAM, NFS_VERSION, options) {
+                Ok(port) => port,
+                Err(err) => {
+                    debug!("failed to query the NFS port: {:?}", err);
+                    NFS_PORT
+                }
+            },
+        };
+
+        Ok(Self {
+            rpc: RpcClient::new(server, port, NFS_PROGRAM, NFS_VERSION, options.clone())?,
+        })
+    }
+
+    pub(super) fn getattr(&self, fh: &FileHandle) -> Result<Fattr> {
+        let reply = self.call(NFSPROC3_GETATTR, |args| fh.encode(args))?;
+        let mut results = reply.results();
+        check_status(&mut results)?;
+        Fattr::decode(&mut results)
+    }
+
+    pub(super) fn setattr(&self, fh: &FileHandle, attrs: &SetAttrs) -> Result<Option<Fattr>> {
+        let reply = self.call(NFSPROC3_SETATTR, |args| {
+            fh.encode(args);
+            attrs.encode(args);
+            // No guard on the ctime
+            args.put_bool(false);
+        })?;
+        let mut results = reply.results();
+        check_status(&mut results)?;
+        Fattr::decode_wcc(&mut results)
+    }
+
+    pub(super) fn lookup(&self, dir: &FileHandle, name: &str) -> Result<(FileHandle, Fattr)> {
+        let reply = self.call(NFSPROC3_LOOKUP, |args| {
+            dir.encode(args);
+            args.put_str(name);
+        })?;
+        let mut results = reply.results();
+        check_status(&mut results)?;
+        let fh = FileHandle::decode(&mut results)?;
+        let attr = match Fattr::decode_post_op(&mut results)? {
+            Some(attr) => attr,
+            None => self.getattr(&fh)?,
+        };
+        Ok((fh, attr))
+    }
+
+    pub(super) fn readlink(&self, fh: &FileHandle) -> Result<String> {
+        let reply = self.call(NFSPROC3_READLINK, |args| fh.encode(args))?;
+        let mut results = reply.results();
+        check_status(&mut results)?;
+        Fattr::decode_post_op(&mut results)?;
+        let target = results.get_opaque(MAX_PATH_LEN)?;
+        Ok(String::from_utf8(target.to_vec())?)
+    }
+
+    /// Reads the data at the offset into the buffer.
+    ///
+    /// This method returns the number of bytes read and the attributes after the read.
+    pub(super) fn read(
+        &self,
+        fh: &FileHandle,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> Result<(usize, Option<Fattr>)> {
+        let reply = self.call(NFSPROC3_READ, |args| {
+            fh.encode(args);
+            args.put_u64(offset).put_u32(buf.len() as u32);
+        })?;
+        let mut results = reply.results();
+        check_status(&mut results)?;
+        let attr = Fattr::decode_post_op(&mut results)?;
+        // The count and the EOF flag
+        results.get_u32()?;
+        results.get_bool()?;
+        let data = results.get_opaque(buf.len())?;
+        buf[..data.len()].copy_from_slice(data);
+        Ok((data.len(), attr))
+    }
+
+    /// Writes the data at the offset synchronously.
+    ///
+    /// This method returns the number of bytes written and the attributes after the write.
+    pub(super) fn write(
+        &self,
+        fh: &FileHandle,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<(usize, Option<Fattr>)> {
+        let reply = self.call(NFSPROC3_WRITE, |args| {
+            fh.encode(args);
+            args.put_u64(offset)
+                .put_u32(data.len() as u32)
+                .put_u32(FILE_SYNC)
+                .put_opaque(data);
+        })?;
+        let mut results = reply.results();
+        check_status(&mut results)?;
+        let attr = Fattr::decode_wcc(&mut results)?;
+        let count = results.get_u32()? as usize;
+        // The `stable_how` and the write verifier, which are not needed for `FILE_SYNC` writes.
+        results.get_u32()?;
+        results.get_fixed_opaque(NFS3_WRITEVERFSIZE)?;
+        Ok((count.min(data.len()), attr))
+    }
+
+    /// Creates a regular file exclusively.
+    pub(super) fn create(
+        &self,
+        dir: &FileHandle,
+        name: &str,
+        attrs: &SetAttrs,
+    ) -> Result<(FileHandle, Fattr)> {
+        let reply = self.call(NFSPROC3_CREATE, |args| {
+            dir.encode(args);
+            args.put_str(name).put_u32(GUARDED);
+            attrs.encode(args);
+        })?;
+        self.decode_created(&reply, dir, name)
+    }
+
+    pub(super) fn mkdir(
+        &self,
+        dir: &FileHandle,
+        name: &str,
+        attrs: &SetAttrs,
+    ) -> Result<(FileHandle, Fattr)> {
+        let reply = self.call(NFSPROC3_MKDIR, |args| {
+            dir.encode(args);
+            args.put_str(name);
+            attrs.encode(args);
+        })?;
+        self.decode_created(&reply, dir, name)
+    }
+
+    pub(super) fn symlink(
+        &self,
+        dir: &FileHandle,
+        name: &str,
+        target: &str,
+        attrs: &SetAttrs,
+    ) -> Result<(FileHandle, Fattr)> {
+        let reply = self.call(NFSPROC3_SYMLINK, |args| {
+            dir.encode(args);
+            args.put_str(name);
+            attrs.encode(args);
+            args.put_str(target);
+        })?;
+        self.decode_created(&reply, dir, name)
+    }
+
+    /// Creates a special file, which is a named pipe, a socket, or a device.
+    pub(super) fn mknod(
+        &self,
+        dir: &FileHandle,
+        name: &str,
+        type_: InodeType,
+        attrs: &SetAttrs,
+        rdev: DeviceId,
+    ) -> Result<(FileHandle, Fattr)> {
+        let reply = self.call(NFSPROC3_MKNOD, |args| {
+            dir.encode(args);
+            args.put_str(name);
+            match type_ {
+                InodeType::CharDevice | InodeType::BlockDevice => {
+                    let ftype = if type_ == InodeType::CharDevice { 4 } else { 3 };
+                    args.put_u32(ftype);
+                    attrs.encode(args);
+                    args.put_u32(rdev.major()).put_u32(rdev.minor());
+                }
+                _ => {
+                    let ftype = if type_ == InodeType::Socket { 6 } else { 7 };
+                    args.put_u32(ftype);
+                    attrs.encode(args);
+                }
+            }
+        })?;
+        self.decode_created(&reply, dir, name)
+    }
+
+    pub(super) fn remove(&self, dir: &FileHandle, name: &str) -> Result<()> {
+        self.call_dir_op(NFSPROC3_REMOVE, dir, name)
+    }
+
+    pub(super) fn rmdir(&self, dir: &FileHandle, name: &str) -> Result<()> {
+        self.call_dir_op(NFSPROC3_RMDIR, dir, name)
+    }
+
+    pub(super) fn rename(
+        &self,
+        from_dir: &FileHandle,
+        from_name: &str,
+        to_dir: &FileHandle,
+        to_name: &str,
+    ) -> Result<()> {
+        let reply = self.call(NFSPROC3_RENAME, |args| {
+            from_dir.encode(args);
+            args.put_str(from_name);
+            to_dir.encode(args);
+            args.put_str(to_name);
+        })?;
+        check_status(&mut reply.results())
+    }
+
+    pub(super) fn link(&self, fh: &FileHandle, dir: &FileHandle, name: &str) -> Result<()> {
+        let reply = self.call(NFSPROC3_LINK, |args| {
+            fh.encode(args);
+            dir.encode(args);
+            args.put_str(name);
+        })?;
+        check_status(&mut reply.results())
+    }
+
+    /// Reads all the entries of the directory.
+    pub(super) fn readdir(&self, dir: &FileHandle) -> Result<Vec<DirEntry>> {
+        let mut entries = Vec::new();
+        let mut cookie = 0u64;
+        let mut cookie_verf = [0u8; NFS3_COOKIEVERFSIZE];
+
+        loop {
+            let reply = self.call(NFSPROC3_READDIRPLUS, |args| {
+                dir.encode(args);
+                args.put_u64(cookie)
+                    .put_fixed_opaque(&cookie_verf)
+                    .put_u32(READDIR_MAX_COUNT / 4)
+                    .put_u32(READDIR_MAX_COUNT);
+            })?;
+            let mut results = reply.results();
+            check_status(&mut results)?;
+            Fattr::decode_post_op(&mut results)?;
+            cookie_verf.copy_from_slice(results.get_fixed_opaque(NFS3_COOKIEVERFSIZE)?);
+
+            while results.get_bool()? {
+                let fileid = results.get_u64()?;
+                let name = results.get_opaque(MAX_NAME_LEN)?;
+                cookie = results.get_u64()?;
+                let attr = Fattr::decode_post_op(&mut results)?;
+                let fh = if results.get_bool()? {
+                    Some(FileHandle::decode(&mut results)?)
+                } else {
+                    None
+                };
+
+                // The names that are not valid UTF-8 cannot be represented in the VFS.
+                let Ok(name) = core::str::from_utf8(name) else {
+                    continue;
+                };
+                entries.push(DirEntry {
+                    name: name.to_string(),
+                    fileid,
+                    handle: fh.zip(attr),
+                });
+            }
+
+            if results.get_bool()? {
+                return Ok(entries);
+            }
+        }
+    }
+
+    pub(super) fn fsinfo(&self, fh: &FileHandle) -> Result<FsInfo> {
+        let reply = self.call(NFSPROC3_FSINFO, |args| fh.encode(args))?;
+        let mut results = reply.results();
+        check_status(&mut results)?;
+        Fattr::decode_post_op(&mut results)?;
+        let rtmax = results.get_u32()?;
+        let rtpref = results.get_u32()?;
+        // The suggested multiple of the read size
+        results.get_u32()?;
+        let wtmax = results.get_u32()?;
+        let wtpref = results.get_u32()?;
+        Ok(FsInfo {
+            rtmax,
+            rtpref,
+            wtmax,
+            wtpref,
+        })
+    }
+
+    pub(super) fn fsstat(&self, fh: &FileHandle) -> Result<FsStat> {
+        let reply = self.call(NFSPROC3_FSSTAT, |args| fh.encode(args))?;
+        let mut results = reply.results();
+        check_status(&mut results)?;
+        Fattr::decode_post_op(&mut results)?;
+        Ok(FsStat {
+            tbytes: results.get_u64()?,
+            fbytes: results.get_u64()?,
+            abytes: results.get_u64()?,
+            tfiles: results.get_u64()?,
+            ffiles: results.get_u64()?,
+        })
+    }
+
+    fn call(&self, procedure: u32, encode_args: impl FnOnce(&mut XdrEncoder)) -> Result<RpcReply> {
+        let mut args = XdrEncoder::new();
+        encode_args(&mut args);
+        self.rpc.call(procedure, &args)
+    }
+
+    fn call_dir_op(&self, procedure: u32, dir: &FileHandle, name: &str) -> Result<()> {
+        let reply = self.call(procedure, |args| {
+            dir.encode(args);
+            args.put_str(name);
+        })?;
+        check_status(&mut reply.results())
+    }
+
+    /// Decodes the results of the procedures that create files.
+    ///
+    /// The servers may omit the file handle or the attributes of the new file, which are then
+    /// looked up.
+    fn decode_created(
+        &self,
+        reply: &RpcReply,
+        dir: &FileHandle,
+        name: &str,
+    ) -> Result<(FileHandle, Fattr)> {
+        let mut results = reply.results();
+        check_status(&mut results)?;
+        let fh = if results.get_bool()? {
+            Some(FileHandle::decode(&mut results)?)
+        } else {
+            None
+        };
+        let attr = Fattr::decode_post_op(&mut results)?;
+
+        match (fh, attr) {
+            (Some(fh), Some(attr)) => Ok((fh, attr)),
+            (Some(fh), None) => {
+                let attr = self.getattr(&fh)?;
+                Ok((fh, attr))
+            }
+            (None, _) => self.lookup(dir, name),
+        }
+    }
+}
+
+/// Checks the status at the beginning of the results.
+fn check_status(results: &mut XdrDecoder) -> Result<()> {
+    match results.get_u32()? {
+        0 => Ok(()),
+        status => Err(status_to_error(status)),
+    }
+}
+
+/// Converts an `nfsstat3` value to an error.
+fn status_to_error(status: u32) -> Error {
+    let errno = match status {
+        1 => Errno::EPERM,
+        2 => Errno::ENOENT,
+        6 => Errno::ENXIO,
+        13 => Errno::EACCES,
+        17 => Errno::EEXIST,
+        18 => Errno::EXDEV,
+        19 => Errno::ENODEV,
+        20 => Errno::ENOTDIR,
+        21 => Errno::EISDIR,
+        22 => Errno::EINVAL,
+        27 => Errno::EFBIG,
+        28 => Errno::ENOSPC,
+        30 => Errno::EROFS,
+        31 => Errno::EMLINK,
+        63 => Errno::ENAMETOOLONG,
+        66 => Errno::ENOTEMPTY,
+        69 => Errno::EDQUOT,
+        70 | 10001 => Errno::ESTALE,
+        71 => Errno::EREMOTE,
+        10004 => Errno::EOPNOTSUPP,
+        // `NFS3ERR_JUKEBOX`
+        10008 => Errno::EAGAIN,
+        10006 => Errno::EREMOTEIO,
+        _ => Errno::EIO,
+    };
+    Error::with_message(errno, "the NFS server returns an error")
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn decode_attributes() {
+        let mut encoder = XdrEncoder::new();
+        // NF3REG, 0o100644 (the file type bits are ignored), nlink, uid, and gid
+        encoder
+            .put_u32(1)
+            .put_u32(0o100644)
+            .put_u32(1)
+            .put_u32(1000)
+            .put_u32(100);
+        // size, used, rdev, fsid, and fileid
+        encoder
+            .put_u64(5000)
+            .put_u64(8192)
+            .put_u32(0)
+            .put_u32(0)
+            .put_u64(1)
+            .put_u64(42);
+        // atime, mtime, and ctime
+        for secs in [10, 20, 30] {
+            encoder.put_u32(secs).put_u32(500);
+        }
+
+        let attr = Fattr::decode(&mut XdrDecoder::new(encoder.as_bytes())).unwrap();
+        assert_eq!(attr.type_, InodeType::File);
+        assert_eq!(attr.mode, 0o644);
+        assert_eq!(attr.uid, 1000);
+        assert_eq!(attr.size, 5000);
+        assert_eq!(attr.fileid, 42);
+        assert_eq!(attr.mtime, Duration::new(20, 500));
+
+        let truncated = &encoder.as_bytes()[..encoder.as_bytes().len() - 4];
+        assert!(Fattr::decode(&mut XdrDecoder::new(truncated)).is_err());
+    }
+
+    #[ktest]
+    fn encode_set_attributes() {
+        let attrs = SetAttrs {
+            mode: Some(0o755),
+            size: Some(0),
+            ..Default::default()
+        };
+        let mut encoder = XdrEncoder::new();
+        attrs.encode(&mut encoder);
+
+        let mut decoder = XdrDecoder::new(encoder.as_bytes());
+        let mut next = || decoder.get_u32().unwrap();
+        assert_eq!([next(), next()], [1, 0o755]);
+        // uid and gid
+        assert_eq!([next(), next()], [0, 0]);
+        assert_eq!([next(), next(), next()], [1, 0, 0]);
+        // atime and mtime
+        assert_eq!([next(), next()], [0, 0]);
+        assert!(decoder.remaining().is_empty());
+    }
+}
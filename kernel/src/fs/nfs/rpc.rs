@@ -0,0 +1,438 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The ONC RPC client (RFC 5531) over UDP and TCP.
+//!
+//! The calls are authenticated with `AUTH_UNIX` (i.e., `AUTH_SYS`) using the file system
+//! credentials of the calling thread, so the server checks the permissions as if the caller
+//! accessed the files locally.
+
+use core::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use aster_bigtcp::wire::Ipv4Address;
+
+use super::xdr::{XdrDecoder, XdrEncoder};
+use crate::{
+    events::IoEvents,
+    net::{
+        namespace::NetNamespace,
+        socket::{
+            ip::{datagram::DatagramSocket, stream::StreamSocket},
+            MessageHeader, SendRecvFlags, Socket, SocketAddr,
+        },
+    },
+    prelude::*,
+    process::{posix_thread::AsPosixThread, signal::Pollable},
+    util::random::getrandom,
+};
+
+const RPC_VERSION: u32 = 2;
+
+const MSG_CALL: u32 = 0;
+const MSG_REPLY: u32 = 1;
+
+const MSG_ACCEPTED: u32 = 0;
+const MSG_DENIED: u32 = 1;
+
+const SUCCESS: u32 = 0;
+const PROG_UNAVAIL: u32 = 1;
+const PROG_MISMATCH: u32 = 2;
+const PROC_UNAVAIL: u32 = 3;
+
+const AUTH_NONE: u32 = 0;
+const AUTH_UNIX: u32 = 1;
+const MAX_AUTH_BYTES: usize = 400;
+const MAX_AUTH_GROUPS: usize = 16;
+
+/// The bit in the record marks that indicates the last fragment of a record (RFC 5531, Section 11).
+const LAST_FRAGMENT: u32 = 1 << 31;
+const MAX_RECORD_LEN: usize = 4 << 20;
+
+const MAX_UDP_MESSAGE_LEN: usize = 65536;
+
+/// The range of the reserved ports that the client binds to, which follows Linux.
+const MIN_RESVPORT: u16 = 665;
+const MAX_RESVPORT: u16 = 1023;
+
+/// The maximum timeout after the exponential backoff of the retransmissions.
+const MAX_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The transport protocol of RPC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Transport {
+    Udp,
+    Tcp,
+}
+
+impl Transport {
+    /// Returns the IP protocol number, which is used by the port mapper.
+    pub(super) fn ip_protocol(self) -> u32 {
+        match self {
+            Self::Udp => 17,
+            Self::Tcp => 6,
+        }
+    }
+}
+
+/// The options of an RPC client.
+#[derive(Debug, Clone)]
+pub(super) struct RpcOptions {
+    pub(super) transport: Transport,
+    /// The time to wait for a reply before retransmitting the call.
+    pub(super) timeout: Duration,
+    /// The number of retransmissions before a call fails.
+    pub(super) retrans: u32,
+    /// Whether to bind to a reserved port, which most servers require by default.
+    pub(super) use_resvport: bool,
+}
+
+/// A client that calls a remote program of a specified version.
+pub(super) struct RpcClient {
+    server: Ipv4Address,
+    port: u16,
+    program: u32,
+    version: u32,
+    options: RpcOptions,
+    next_xid: AtomicU32,
+    // TODO: Multiplex the calls over the connection instead of serializing them.
+    conn: Mutex<Option<Connection>>,
+}
+
+impl RpcClient {
+    pub(super) fn new(
+        server: Ipv4Address,
+        port: u16,
+        program: u32,
+        version: u32,
+        options: RpcOptions,
+    ) -> Result<Self> {
+        let mut xid = [0u8; 4];
+        getrandom(&mut xid)?;
+
+        Ok(Self {
+            server,
+            port,
+            program,
+            version,
+            options,
+            next_xid: AtomicU32::new(u32::from_ne_bytes(xid)),
+            conn: Mutex::new(None),
+        })
+    }
+
+    /// Calls the remote procedure with the encoded arguments.
+    ///
+    /// The call is retransmitted if no reply is received in time, and fails with `EIO` after
+    /// all the retransmissions time out.
+    pub(super) fn call(&self, procedure: u32, args: &XdrEncoder) -> Result<RpcReply> {
+        let xid = self.next_xid.fetch_add(1, Ordering::Relaxed);
+        let msg = encode_call(xid, self.program, self.version, procedure, args);
+
+        let mut conn = self.conn.lock();
+        let mut timeout = self.options.timeout;
+        for _ in 0..=self.options.retrans {
+            let res = match conn.as_ref() {
+                Some(established) => established.transact(msg.as_bytes(), xid, &timeout),
+                None => self.connect().and_then(|new_conn| {
+                    conn.insert(new_conn)
+                        .transact(msg.as_bytes(), xid, &timeout)
+                }),
+            };
+
+            match res {
+                Ok(reply) => return RpcReply::parse(reply),
+                Err(err) if err.error() == Errno::ETIMEDOUT => {
+                    if self.options.transport == Transport::Udp {
+                        timeout = (timeout * 2).min(MAX_TIMEOUT);
+                    } else {
+                        *conn = None;
+                    }
+                }
+                Err(err)
+                    if self.options.transport == Transport::Tcp
+                        && matches!(
+                            err.error(),
+                            Errno::ECONNRESET
+                                | Errno::ECONNABORTED
+                                | Errno::EPIPE
+                                | Errno::ENOTCONN
+                        ) =>
+                {
+                    *conn = None;
+                }
+                Err(err) => return Err(err),
+            }
+            debug!(
+                "retransmitting the RPC call {:#x} to {:?}",
+                xid, self.server
+            );
+        }
+
+        return_errno_with_message!(Errno::EIO, "the RPC server is not responding");
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        let remote_addr = || SocketAddr::IPv4(self.server, self.port);
+        // FIXME: The server should be connected in the network namespace of the mounter.
+        let net_ns = || NetNamespace::get_init_singleton().clone();
+
+        match self.options.transport {
+            Transport::Udp => {
+                let socket = DatagramSocket::new(true, net_ns());
+                if self.options.use_resvport {
+                    bind_resvport(socket.as_ref())?;
+                }
+                socket.connect(remote_addr())?;
+                Ok(Connection::Udp(socket))
+            }
+            Transport::Tcp => {
+                let socket = StreamSocket::new(true, net_ns());
+                if self.options.use_resvport {
+                    bind_resvport(socket.as_ref())?;
+                }
+                match socket.connect(remote_addr()) {
+                    Err(err) if err.error() == Errno::EINPROGRESS => (),
+                    res => res?,
+                }
+                socket
+                    .wait_events(IoEvents::OUT, Some(&self.options.timeout), || match socket
+                        .connect(remote_addr())
+                    {
+                        Err(err) if err.error() == Errno::EALREADY => {
+                            return_errno_with_message!(Errno::EAGAIN, "the socket is connecting")
+                        }
+                        res => res,
+                    })
+                    .map_err(map_timeout)?;
+                Ok(Connection::Tcp(socket))
+            }
+        }
+    }
+}
+
+/// An accepted and successful reply.
+pub(super) struct RpcReply {
+    msg: Vec<u8>,
+    results_offset: usize,
+}
+
+impl RpcReply {
+    fn parse(msg: Vec<u8>) -> Result<Self> {
+        let mut decoder = XdrDecoder::new(&msg);
+        // The XID has been checked.
+        decoder.get_u32()?;
+        if decoder.get_u32()? != MSG_REPLY {
+            return_errno_with_message!(Errno::EBADMSG, "the RPC message is not a reply");
+        }
+
+        match decoder.get_u32()? {
+            MSG_ACCEPTED => (),
+            MSG_DENIED => {
+                return_errno_with_message!(Errno::EACCES, "the RPC call is denied")
+            }
+            _ => return_errno_with_message!(Errno::EBADMSG, "the RPC reply status is invalid"),
+        }
+
+        // The verifier
+        decoder.get_u32()?;
+        decoder.get_opaque(MAX_AUTH_BYTES)?;
+
+        match decoder.get_u32()? {
+            SUCCESS => (),
+            PROG_UNAVAIL | PROG_MISMATCH => return_errno_with_message!(
+                Errno::EPROTONOSUPPORT,
+                "the RPC program or version is not supported"
+            ),
+            PROC_UNAVAIL => {
+                return_errno_with_message!(Errno::EOPNOTSUPP, "the RPC procedure is not supported")
+            }
+            _ => return_errno_with_message!(Errno::EIO, "the RPC server fails to execute the call"),
+        }
+
+        let results_offset = msg.len() - decoder.remaining().len();
+        Ok(Self {
+            msg,
+            results_offset,
+        })
+    }
+
+    /// Returns a decoder of the results of the procedure.
+    pub(super) fn results(&self) -> XdrDecoder<'_> {
+        XdrDecoder::new(&self.msg[self.results_offset..])
+    }
+}
+
+enum Connection {
+    Udp(Arc<DatagramSocket>),
+    Tcp(Arc<StreamSocket>),
+}
+
+impl Connection {
+    /// Sends the call and receives the reply with the same XID.
+    fn transact(&self, msg: &[u8], xid: u32, timeout: &Duration) -> Result<Vec<u8>> {
+        match self {
+            Self::Udp(socket) => {
+                socket.sendmsg(
+                    &mut VmReader::from(msg).to_fallible(),
+                    MessageHeader::new(None, Vec::new()),
+                    SendRecvFlags::empty(),
+                )?;
+
+                let mut buf = vec![0u8; MAX_UDP_MESSAGE_LEN];
+                loop {
+                    let len = socket
+                        .wait_events(IoEvents::IN, Some(timeout), || {
+                            let mut writer = VmWriter::from(buf.as_mut_slice()).to_fallible();
+                            socket
+                                .recvmsg(&mut writer, SendRecvFlags::empty())
+                                .map(|(len, _)| len)
+                        })
+                        .map_err(map_timeout)?;
+                    // Ignore the late replies to the previous calls.
+                    if buf[..len].starts_with(&xid.to_be_bytes()) {
+                        buf.truncate(len);
+                        return Ok(buf);
+                    }
+                }
+            }
+            Self::Tcp(socket) => {
+                let mut record = Vec::with_capacity(4 + msg.len());
+                record.extend((LAST_FRAGMENT | msg.len() as u32).to_be_bytes());
+                record.extend_from_slice(msg);
+                send_all(socket, &record, timeout)?;
+
+                loop {
+                    let reply = recv_record(socket, timeout)?;
+                    if reply.starts_with(&xid.to_be_bytes()) {
+                        return Ok(reply);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn send_all(socket: &StreamSocket, mut buf: &[u8], timeout: &Duration) -> Result<()> {
+    while !buf.is_empty() {
+        let len = socket
+            .wait_events(IoEvents::OUT, Some(timeout), || {
+                socket.sendmsg(
+                    &mut VmReader::from(buf).to_fallible(),
+                    MessageHeader::new(None, Vec::new()),
+                    SendRecvFlags::MSG_NOSIGNAL,
+                )
+            })
+            .map_err(map_timeout)?;
+        buf = &buf[len..];
+    }
+    Ok(())
+}
+
+fn recv_record(socket: &StreamSocket, timeout: &Duration) -> Result<Vec<u8>> {
+    let mut record = Vec::new();
+    loop {
+        let mut mark = [0u8; 4];
+        recv_exact(socket, &mut mark, timeout)?;
+        let mark = u32::from_be_bytes(mark);
+
+        let len = (mark & !LAST_FRAGMENT) as usize;
+        if record.len() + len > MAX_RECORD_LEN {
+            return_errno_with_message!(Errno::EBADMSG, "the RPC record is too long");
+        }
+        let start = record.len();
+        record.resize(start + len, 0);
+        recv_exact(socket, &mut record[start..], timeout)?;
+
+        if mark & LAST_FRAGMENT != 0 {
+            return Ok(record);
+        }
+    }
+}
+
+fn recv_exact(socket: &StreamSocket, buf: &mut [u8], timeout: &Duration) -> Result<()> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let len = socket
+            .wait_events(IoEvents::IN, Some(timeout), || {
+                let mut writer = VmWriter::from(&mut buf[filled..]).to_fallible();
+                socket
+                    .recvmsg(&mut writer, SendRecvFlags::empty())
+                    .map(|(len, _)| len)
+            })
+            .map_err(map_timeout)?;
+        if len == 0 {
+            return_errno_with_message!(Errno::ECONNRESET, "the RPC server closes the connection");
+        }
+        filled += len;
+    }
+    Ok(())
+}
+
+fn bind_resvport(socket: &dyn Socket) -> Result<()> {
+    for port in (MIN_RESVPORT..=MAX_RESVPORT).rev() {
+        match socket.bind(SocketAddr::IPv4(Ipv4Address::new(0, 0, 0, 0), port)) {
+            Err(err) if err.error() == Errno::EADDRINUSE => continue,
+            res => return res,
+        }
+    }
+    return_errno_with_message!(Errno::EADDRINUSE, "no reserved ports are available");
+}
+
+fn map_timeout(err: Error) -> Error {
+    if err.error() == Errno::ETIME {
+        Error::with_message(Errno::ETIMEDOUT, "the RPC call timed out")
+    } else {
+        err
+    }
+}
+
+fn encode_call(
+    xid: u32,
+    program: u32,
+    version: u32,
+    procedure: u32,
+    args: &XdrEncoder,
+) -> XdrEncoder {
+    let mut msg = XdrEncoder::new();
+    msg.put_u32(xid)
+        .put_u32(MSG_CALL)
+        .put_u32(RPC_VERSION)
+        .put_u32(program)
+        .put_u32(version)
+        .put_u32(procedure);
+
+    // The credential
+    let (uid, gid, groups) = match current_thread!().as_posix_thread() {
+        Some(posix_thread) => {
+            let credentials = posix_thread.credentials();
+            let groups = credentials
+                .groups()
+                .iter()
+                .take(MAX_AUTH_GROUPS)
+                .map(|gid| u32::from(*gid))
+                .collect();
+            (
+                u32::from(credentials.fsuid()),
+                u32::from(credentials.fsgid()),
+                groups,
+            )
+        }
+        None => (0, 0, Vec::new()),
+    };
+    let mut auth = XdrEncoder::new();
+    // The stamp and the machine name, which are not used by the servers for authentication.
+    auth.put_u32(0).put_str("").put_u32(uid).put_u32(gid);
+    auth.put_u32(groups.len() as u32);
+    for gid in groups {
+        auth.put_u32(gid);
+    }
+    msg.put_u32(AUTH_UNIX).put_opaque(auth.as_bytes());
+
+    // The verifier
+    msg.put_u32(AUTH_NONE).put_opaque(&[]);
+
+    msg.put_fixed_opaque(args.as_bytes());
+    msg
+}
@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The External Data Representation (XDR) of the ONC RPC messages (RFC 4506).
+//!
+//! All the items are big-endian and are padded to multiples of four bytes.
+
+use crate::prelude::*;
+
+/// An encoder that appends XDR items to a buffer.
+#[derive(Debug, Default)]
+pub(super) struct XdrEncoder {
+    buf: Vec<u8>,
+}
+
+impl XdrEncoder {
+    pub(super) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(super) fn put_u32(&mut self, value: u32) -> &mut Self {
+        self.buf.extend(value.to_be_bytes());
+        self
+    }
+
+    pub(super) fn put_u64(&mut self, value: u64) -> &mut Self {
+        self.buf.extend(value.to_be_bytes());
+        self
+    }
+
+    pub(super) fn put_bool(&mut self, value: bool) -> &mut Self {
+        self.put_u32(value as u32)
+    }
+
+    /// Puts a fixed-length opaque item, whose length is known to the decoder.
+    pub(super) fn put_fixed_opaque(&mut self, bytes: &[u8]) -> &mut Self {
+        self.buf.extend_from_slice(bytes);
+        self.buf.resize(self.buf.len().next_multiple_of(4), 0);
+        self
+    }
+
+    /// Puts a variable-length opaque item, which is prefixed with its length.
+    pub(super) fn put_opaque(&mut self, bytes: &[u8]) -> &mut Self {
+        self.put_u32(bytes.len() as u32).put_fixed_opaque(bytes)
+    }
+
+    pub(super) fn put_str(&mut self, s: &str) -> &mut Self {
+        self.put_opaque(s.as_bytes())
+    }
+
+    pub(super) fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+/// A decoder that reads XDR items from a buffer in order.
+#[derive(Debug)]
+pub(super) struct XdrDecoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> XdrDecoder<'a> {
+    pub(super) fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    pub(super) fn get_u32(&mut self) -> Result<u32> {
+        let bytes = self.get_bytes(4)?;
+        Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub(super) fn get_u64(&mut self) -> Result<u64> {
+        let bytes = self.get_bytes(8)?;
+        Ok(u64::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub(super) fn get_bool(&mut self) -> Result<bool> {
+        match self.get_u32()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => return_errno_with_message!(Errno::EBADMSG, "the XDR boolean is invalid"),
+        }
+    }
+
+    pub(super) fn get_fixed_opaque(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self.get_bytes(len)?;
+        self.skip(len.next_multiple_of(4) - len)?;
+        Ok(bytes)
+    }
+
+    /// Gets a variable-length opaque item that is no longer than `max_len`.
+    pub(super) fn get_opaque(&mut self, max_len: usize) -> Result<&'a [u8]> {
+        let len = self.get_u32()? as usize;
+        if len > max_len {
+            return_errno_with_message!(Errno::EBADMSG, "the XDR opaque data is too long");
+        }
+        self.get_fixed_opaque(len)
+    }
+
+    pub(super) fn skip(&mut self, len: usize) -> Result<()> {
+        self.get_bytes(len).map(|_| ())
+    }
+
+    /// Returns the bytes that have not been decoded.
+    pub(super) fn remaining(&self) -> &'a [u8] {
+        &self.buf[self.pos..]
+    }
+
+    fn get_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let Some(bytes) = self.buf.get(self.pos..self.pos + len) else {
+            return_errno_with_message!(Errno::EBADMSG, "the XDR data is truncated");
+        };
+        self.pos += len;
+        Ok(bytes)
+    }
+}
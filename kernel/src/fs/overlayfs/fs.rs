@@ -11,7 +11,7 @@ use core::{
 use align_ext::AlignExt;
 use aster_block::BLOCK_SIZE;
 use aster_rights::Full;
-use hashbrown::HashSet;
+use hashbrown::{HashMap, HashSet};
 use inherit_methods_macro::inherit_methods;
 use ostd::mm::{FrameAllocOptions, UntypedMem};
 
@@ -91,6 +91,10 @@ struct OverlayInode {
     upper_is_opaque: bool,
     /// The immutable lower layered regular inodes.
     lowers: Vec<Arc<dyn Inode>>,
+    /// The children that have been looked up or created.
+    /// Keeping the same `OverlayInode` for a child ensures that the state of
+    /// the copy-up is shared among all its users.
+    children: Mutex<HashMap<String, Weak<OverlayInode>>>,
     /// Weak fs reference.
     fs: Weak<OverlayFS>,
     /// Weak self reference.
@@ -131,6 +135,7 @@ impl FileSystem for OverlayFS {
                 .map(|dentry| dentry.inode())
                 .cloned()
                 .collect(),
+            children: Mutex::new(HashMap::new()),
             fs: self.self_.clone(),
             self_: weak.clone(),
         })
@@ -230,9 +235,11 @@ impl OverlayInode {
             upper: Mutex::new(Some(new_upper)),
             upper_is_opaque,
             lowers: Vec::new(),
+            children: Mutex::new(HashMap::new()),
             fs: self.fs.clone(),
             self_: weak.clone(),
         });
+        self.cache_child(name, &new_child);
         Ok(new_child)
     }
 
@@ -310,18 +317,10 @@ impl OverlayInode {
             assert!(target_has_valid_lower);
         }
 
+        self.children.lock().remove(name);
+
         if target_has_valid_lower {
-            let whiteout = upper.create(
-                &whiteout_name(name),
-                InodeType::File,
-                InodeMode::from_bits_truncate(0o644),
-            )?;
-            // FIXME: Align the whiteout xattr behavior with Linux
-            whiteout.set_xattr(
-                XattrName::try_from_full_name(WHITEOUT_XATTR_NAME).unwrap(),
-                &mut VmReader::from(WHITEOUT_AND_OPAQUE_XATTR_VALUE.as_slice()).to_fallible(),
-                XattrSetFlags::CREATE_ONLY,
-            )?;
+            create_whiteout(upper, name)?;
         }
 
         Ok(())
@@ -360,18 +359,8 @@ impl OverlayInode {
         }
 
         upper.rmdir(name)?;
-
-        let whiteout = upper.create(
-            &whiteout_name(name),
-            InodeType::File,
-            InodeMode::from_bits_truncate(0o644),
-        )?;
-        // FIXME: Align the whiteout xattr behavior with Linux
-        whiteout.set_xattr(
-            XattrName::try_from_full_name(WHITEOUT_XATTR_NAME).unwrap(),
-            &mut VmReader::from(WHITEOUT_AND_OPAQUE_XATTR_VALUE.as_slice()).to_fallible(),
-            XattrSetFlags::CREATE_ONLY,
-        )?;
+        self.children.lock().remove(name);
+        create_whiteout(upper, name)?;
 
         Ok(())
     }
@@ -418,16 +407,41 @@ impl OverlayInode {
         if self.type_ != InodeType::Dir {
             return_errno_with_message!(Errno::ENOTDIR, "not mknod on a dir");
         }
-        let upper = self.build_upper_recursively_if_needed()?;
-        upper.mknod(name, mode, type_)
+        let upper = self.prepare_upper_for_new_child(name)?;
+        let new_upper = upper.mknod(name, mode, type_)?;
+
+        let new_child = Arc::new_cyclic(|weak| OverlayInode {
+            ino: new_upper.ino(),
+            type_: new_upper.type_(),
+            name_upon_creation: SpinLock::new(String::from(name)),
+            parent: Some(self.self_.upgrade().unwrap()),
+            upper: Mutex::new(Some(new_upper)),
+            upper_is_opaque: false,
+            lowers: Vec::new(),
+            children: Mutex::new(HashMap::new()),
+            fs: self.fs.clone(),
+            self_: weak.clone(),
+        });
+        self.cache_child(name, &new_child);
+        Ok(new_child)
     }
 
+    /// Creates a hard link to the old inode in the upper layer.
+    /// The old inode will be copied up first if it resides in the lower layer.
     pub fn link(&self, old: &Arc<dyn Inode>, name: &str) -> Result<()> {
         if self.type_ != InodeType::Dir {
             return_errno_with_message!(Errno::ENOTDIR, "self is not dir");
         }
-        let upper = self.build_upper_recursively_if_needed()?;
-        upper.link(old, name)
+        let old = old
+            .downcast_ref::<OverlayInode>()
+            .ok_or(Error::new(Errno::EXDEV))?;
+        if !Arc::ptr_eq(&self.overlay_fs(), &old.overlay_fs()) {
+            return_errno_with_message!(Errno::EXDEV, "not same fs");
+        }
+
+        let old_upper = old.build_upper_recursively_if_needed()?;
+        let upper = self.prepare_upper_for_new_child(name)?;
+        upper.link(&old_upper, name)
     }
 
     pub fn read_link(&self) -> Result<String> {
@@ -445,13 +459,81 @@ impl OverlayInode {
         upper.write_link(target)
     }
 
-    pub fn rename(&self, _old_name: &str, _target: &Arc<dyn Inode>, _new_name: &str) -> Result<()> {
-        // TODO: Support the rename operation based on the `redirect_mode` feature,
+    /// Renames the target file by renaming its copied-up upper inode.
+    /// A whiteout file is left at the old name if the file also resides in the lower layer.
+    ///
+    /// Like Linux without the `redirect_dir` feature, the directories that reside
+    /// in the lower layer cannot be renamed, so `EXDEV` is returned to let
+    /// the user space fall back to copying.
+    pub fn rename(&self, old_name: &str, target: &Arc<dyn Inode>, new_name: &str) -> Result<()> {
+        // TODO: Support renaming the lower directories based on the `redirect_mode` feature,
         // rename the upper only may unexpectedly reveal the lower inodes.
-        return_errno_with_message!(
-            Errno::EOPNOTSUPP,
-            "rename is not supported in current overlayfs"
-        );
+        if self.type_ != InodeType::Dir {
+            return_errno_with_message!(Errno::ENOTDIR, "self is not dir");
+        }
+        let target = target
+            .downcast_ref::<OverlayInode>()
+            .ok_or(Error::new(Errno::EXDEV))?;
+        if !Arc::ptr_eq(&self.overlay_fs(), &target.overlay_fs()) {
+            return_errno_with_message!(Errno::EXDEV, "not same fs");
+        }
+        if target.type_ != InodeType::Dir {
+            return_errno_with_message!(Errno::ENOTDIR, "target is not dir");
+        }
+
+        // TODO: Hold the upper locks from here to avoid race condition
+        let Some(old) = self.lookup_inner(old_name)? else {
+            return_errno!(Errno::ENOENT);
+        };
+        if old.type_ == InodeType::Dir && old.has_valid_lower() {
+            return_errno_with_message!(Errno::EXDEV, "the directory resides in the lower layer");
+        }
+
+        let new_is_whiteout = match target.lookup_inner(new_name) {
+            Ok(Some(new)) => {
+                if Arc::ptr_eq(&old, &new) {
+                    return Ok(());
+                }
+                match (old.type_, new.type_) {
+                    (InodeType::Dir, InodeType::Dir) => {
+                        if new.has_valid_lower() {
+                            return_errno_with_message!(
+                                Errno::EXDEV,
+                                "the directory resides in the lower layer"
+                            );
+                        }
+                        if new.readdir_inner(0)?.visited_files() > 0 {
+                            return_errno!(Errno::ENOTEMPTY);
+                        }
+                    }
+                    (InodeType::Dir, _) => return_errno!(Errno::ENOTDIR),
+                    (_, InodeType::Dir) => return_errno!(Errno::EISDIR),
+                    _ => (),
+                }
+                false
+            }
+            Ok(None) => true,
+            Err(e) if e.error() == Errno::ENOENT => false,
+            Err(e) => return Err(e),
+        };
+
+        let old_has_valid_lower = old.has_valid_lower();
+        old.build_upper_recursively_if_needed()?;
+        let upper = self.build_upper_recursively_if_needed()?;
+        let target_upper = target.build_upper_recursively_if_needed()?;
+
+        if new_is_whiteout {
+            target_upper.unlink(&whiteout_name(new_name))?;
+        }
+        upper.rename(old_name, &target_upper, new_name)?;
+        self.children.lock().remove(old_name);
+        if old_has_valid_lower {
+            create_whiteout(&upper, old_name)?;
+        }
+
+        *old.name_upon_creation.lock() = String::from(new_name);
+        target.cache_child(new_name, &old);
+        Ok(())
     }
 
     pub fn sync_all(&self) -> Result<()> {
@@ -561,6 +643,20 @@ impl OverlayInode {
         self.name_upon_creation.lock().clone()
     }
 
+    /// Returns the cached child with the given name if it is still in use.
+    fn cached_child(&self, name: &str) -> Option<Arc<OverlayInode>> {
+        self.children.lock().get(name).and_then(Weak::upgrade)
+    }
+
+    fn cache_child(&self, name: &str, child: &Arc<OverlayInode>) {
+        let mut children = self.children.lock();
+        // Drop the children that are no longer in use from time to time.
+        if children.len().is_power_of_two() {
+            children.retain(|_, child| child.strong_count() > 0);
+        }
+        children.insert(String::from(name), Arc::downgrade(child));
+    }
+
     fn overlay_fs(&self) -> Arc<OverlayFS> {
         self.fs.upgrade().unwrap()
     }
@@ -568,10 +664,13 @@ impl OverlayInode {
     /// Lookups the target regular inodes in a layered manner then
     /// builds the corresponding `OverlayInode`.
     /// The whiteout and opaque checks are performed here only.
-    fn lookup_inner(&self, name: &str) -> Result<Option<Arc<dyn Inode>>> {
+    fn lookup_inner(&self, name: &str) -> Result<Option<Arc<OverlayInode>>> {
         if self.type_ != InodeType::Dir {
             return_errno!(Errno::ENOTDIR);
         }
+        if let Some(child) = self.cached_child(name) {
+            return Ok(Some(child));
+        }
 
         let mut type_ = None;
         let mut upper_is_opaque = false;
@@ -658,9 +757,11 @@ impl OverlayInode {
             upper: Mutex::new(upper_child),
             upper_is_opaque,
             lowers: lower_children,
+            children: Mutex::new(HashMap::new()),
             fs: self.fs.clone(),
             self_: weak.clone(),
         });
+        self.cache_child(name, &child_ovl_inode);
 
         Ok(Some(child_ovl_inode))
     }
@@ -745,6 +846,23 @@ impl OverlayInode {
         Ok(new_upper)
     }
 
+    /// Prepares the upper inode for creating a new child with the given name.
+    /// The whiteout file of the child, if any, will be removed.
+    fn prepare_upper_for_new_child(&self, name: &str) -> Result<Arc<dyn Inode>> {
+        let is_whiteout = match self.lookup_inner(name) {
+            Ok(Some(_)) => return_errno!(Errno::EEXIST),
+            Ok(None) => true,
+            Err(e) if e.error() == Errno::ENOENT => false,
+            Err(e) => return Err(e),
+        };
+
+        let upper = self.build_upper_recursively_if_needed()?;
+        if is_whiteout {
+            upper.unlink(&whiteout_name(name))?;
+        }
+        Ok(upper)
+    }
+
     /// Do the "copy-up" operation for the given upper inode.
     fn do_copy_up(&self, upper_inode: &Arc<dyn Inode>) -> Result<()> {
         if self.lowers.is_empty() {
@@ -854,6 +972,22 @@ fn whiteout_name(name: &str) -> String {
     format!("{}{}", WHITEOUT_PREFIX, name)
 }
 
+/// Creates a whiteout file in the upper directory to hide the lower file with the given name.
+fn create_whiteout(upper: &Arc<dyn Inode>, name: &str) -> Result<()> {
+    let whiteout = upper.create(
+        &whiteout_name(name),
+        InodeType::File,
+        InodeMode::from_bits_truncate(0o644),
+    )?;
+    // FIXME: Align the whiteout xattr behavior with Linux
+    whiteout.set_xattr(
+        XattrName::try_from_full_name(WHITEOUT_XATTR_NAME).unwrap(),
+        &mut VmReader::from(WHITEOUT_AND_OPAQUE_XATTR_VALUE.as_slice()).to_fallible(),
+        XattrSetFlags::CREATE_ONLY,
+    )?;
+    Ok(())
+}
+
 fn is_opaque_dir(inode: &Arc<dyn Inode>) -> Result<bool> {
     assert_eq!(inode.type_(), InodeType::Dir);
 
@@ -1301,4 +1435,78 @@ mod tests {
         link.write_link(link_str).unwrap();
         assert_eq!(link.read_link().unwrap(), link_str.to_string());
     }
+
+    #[ktest]
+    fn rename_files() {
+        let fs = create_overlay_fs();
+        let root = fs.root_inode();
+
+        let f2 = root.lookup("f2").unwrap();
+        root.rename("f2", &root, "f3").unwrap();
+        let e = root.lookup("f2").expect_err("");
+        assert_eq!(e.error(), Errno::ENOENT);
+
+        // The renamed file is still the same one for its users.
+        let f3 = root.lookup("f3").unwrap();
+        f2.write_bytes_at(0, &[9u8; 1]).unwrap();
+        let mut data = [0u8; 4];
+        f3.read_bytes_at(0, data.as_mut_slice()).unwrap();
+        assert_eq!(data, [9u8, 8, 8, 8]);
+
+        let d1 = root.lookup("d1").unwrap();
+        d1.rename("f12", &root, "f3").unwrap();
+        assert_eq!(root.lookup("f3").unwrap().size(), 0);
+        let mut d1_fnames = Vec::<String>::new();
+        let _ = d1.readdir_at(0, &mut d1_fnames).unwrap();
+        assert_eq!(d1_fnames, [".", "..", "f11"]);
+
+        root.rename("f3", &root, "f2").unwrap();
+        root.lookup("f2").unwrap();
+    }
+
+    #[ktest]
+    fn rename_dirs() {
+        let fs = create_overlay_fs();
+        let root = fs.root_inode();
+        let mode = InodeMode::all();
+
+        let e = root.rename("d1", &root, "d2").expect_err("");
+        assert_eq!(e.error(), Errno::EXDEV);
+
+        let d2 = root.create("d2", InodeType::Dir, mode).unwrap();
+        d2.create("f21", InodeType::File, mode).unwrap();
+        root.create("d3", InodeType::Dir, mode).unwrap();
+        let e = root.rename("d2", &root, "f1").expect_err("");
+        assert_eq!(e.error(), Errno::ENOTDIR);
+        let e = root.rename("d3", &root, "d2").expect_err("");
+        assert_eq!(e.error(), Errno::ENOTEMPTY);
+
+        root.rename("d2", &root, "d3").unwrap();
+        root.lookup("d3").unwrap().lookup("f21").unwrap();
+    }
+
+    #[ktest]
+    fn mknod_and_link() {
+        let fs = create_overlay_fs();
+        let root = fs.root_inode();
+        let mode = InodeMode::all();
+
+        let e = root
+            .mknod("f1", mode, MknodType::NamedPipeNode)
+            .expect_err("");
+        assert_eq!(e.error(), Errno::EEXIST);
+        root.unlink("f1").unwrap();
+        let pipe = root.mknod("f1", mode, MknodType::NamedPipeNode).unwrap();
+        assert_eq!(pipe.type_(), InodeType::NamedPipe);
+        assert_eq!(root.lookup("f1").unwrap().type_(), InodeType::NamedPipe);
+
+        let f2 = root.lookup("f2").unwrap();
+        root.link(&f2, "f2_link").unwrap();
+        let mut data = [0u8; 4];
+        root.lookup("f2_link")
+            .unwrap()
+            .read_bytes_at(0, data.as_mut_slice())
+            .unwrap();
+        assert_eq!(data, [8u8; 4]);
+    }
 }
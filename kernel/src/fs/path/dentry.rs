@@ -15,6 +15,7 @@ use ostd::sync::RwMutexWriteGuard;
 use super::{is_dot, is_dot_or_dotdot, is_dotdot};
 use crate::{
     fs::{
+        inotify::{self, InotifyMask},
         path::mount::MountNode,
         utils::{
             FileSystem, Inode, InodeMode, InodeType, Metadata, MknodType, Permission, XattrName,
@@ -159,6 +160,7 @@ impl Dentry_ {
         }
 
         let new_inode = self.inode.create(name, type_, mode)?;
+        inotify::notify_create(self.inode.as_ref(), name, type_);
         let name = String::from(name);
         let new_child = Dentry_::new(new_inode, DentryOptions::Leaf((name.clone(), self.this())));
 
@@ -210,6 +212,7 @@ impl Dentry_ {
         }
 
         let inode = self.inode.mknod(name, mode, type_)?;
+        inotify::notify_create(self.inode.as_ref(), name, inode.type_());
         let name = String::from(name);
         let new_child = Dentry_::new(inode, DentryOptions::Leaf((name.clone(), self.this())));
 
@@ -233,6 +236,8 @@ impl Dentry_ {
 
         let old_inode = old.inode();
         self.inode.link(old_inode, name)?;
+        inotify::notify_event(old_inode.as_ref(), None, InotifyMask::IN_ATTRIB);
+        inotify::notify_create(self.inode.as_ref(), name, old_inode.type_());
         let name = String::from(name);
         let dentry = Dentry_::new(
             old_inode.clone(),
@@ -254,7 +259,9 @@ impl Dentry_ {
         let children = self.children.upread();
         children.check_mountpoint(name)?;
 
+        let child = self.lookup_for_notification(name);
         self.inode.unlink(name)?;
+        inotify::notify_delete(self.inode.as_ref(), name, child.as_deref());
 
         let mut children = children.upgrade();
         children.delete(name);
@@ -270,7 +277,9 @@ impl Dentry_ {
         let children = self.children.upread();
         children.check_mountpoint(name)?;
 
+        let child = self.lookup_for_notification(name);
         self.inode.rmdir(name)?;
+        inotify::notify_delete(self.inode.as_ref(), name, child.as_deref());
 
         let mut children = children.upgrade();
         children.delete(name);
@@ -296,7 +305,17 @@ impl Dentry_ {
             let old_dentry = children.check_mountpoint_then_find(old_name)?;
             children.check_mountpoint(new_name)?;
 
+            let child = self.lookup_for_notification(old_name);
+            let replaced = self.lookup_for_notification(new_name);
             self.inode.rename(old_name, &self.inode, new_name)?;
+            inotify::notify_move(
+                self.inode.as_ref(),
+                old_name,
+                self.inode.as_ref(),
+                new_name,
+                child.as_deref(),
+                replaced.as_deref(),
+            );
 
             let mut children = children.upgrade();
             match old_dentry.as_ref() {
@@ -318,7 +337,17 @@ impl Dentry_ {
             let old_dentry = self_children.check_mountpoint_then_find(old_name)?;
             new_dir_children.check_mountpoint(new_name)?;
 
+            let child = self.lookup_for_notification(old_name);
+            let replaced = new_dir.lookup_for_notification(new_name);
             self.inode.rename(old_name, &new_dir.inode, new_name)?;
+            inotify::notify_move(
+                self.inode.as_ref(),
+                old_name,
+                new_dir.inode.as_ref(),
+                new_name,
+                child.as_deref(),
+                replaced.as_deref(),
+            );
             match old_dentry.as_ref() {
                 Some(dentry) => {
                     self_children.delete(old_name);
@@ -334,6 +363,36 @@ impl Dentry_ {
         }
         Ok(())
     }
+
+    /// Resizes the inner inode.
+    pub fn resize(&self, new_size: usize) -> Result<()> {
+        self.inode.resize(new_size)?;
+        self.notify_event(InotifyMask::IN_MODIFY);
+        Ok(())
+    }
+
+    /// Reports an inotify event of the inner inode.
+    pub fn notify_event(&self, mask: InotifyMask) {
+        if !inotify::is_watching() {
+            return;
+        }
+
+        let name_and_parent = self.name_and_parent.read().clone();
+        let parent = name_and_parent
+            .as_ref()
+            .map(|(name, parent)| (parent.inode.as_ref(), name.as_str()));
+        inotify::notify_event(self.inode.as_ref(), parent, mask);
+    }
+
+    /// Looks up the child inode that is going to be removed, so that its watchers can be
+    /// notified.
+    fn lookup_for_notification(&self, name: &str) -> Option<Arc<dyn Inode>> {
+        if !inotify::is_watching() {
+            return None;
+        }
+
+        self.inode.lookup(name).ok()
+    }
 }
 
 #[inherit_methods(from = "self.inode")]
@@ -345,7 +404,6 @@ impl Dentry_ {
     pub fn mode(&self) -> Result<InodeMode>;
     pub fn set_mode(&self, mode: InodeMode) -> Result<()>;
     pub fn size(&self) -> usize;
-    pub fn resize(&self, size: usize) -> Result<()>;
     pub fn owner(&self) -> Result<Uid>;
     pub fn set_owner(&self, uid: Uid) -> Result<()>;
     pub fn group(&self) -> Result<Gid>;
@@ -783,6 +841,7 @@ impl Dentry {
     pub fn inode(&self) -> &Arc<dyn Inode>;
     pub fn is_root_of_mount(&self) -> bool;
     pub fn is_mountpoint(&self) -> bool;
+    pub fn notify_event(&self, mask: InotifyMask);
     pub fn set_xattr(
         &self,
         name: XattrName,
@@ -10,13 +10,20 @@ use crate::{
     events::IoEvents,
     prelude::*,
     process::{
-        signal::{PollHandle, Pollable},
+        posix_thread::AsPosixThread,
+        signal::{constants::SIGPIPE, signals::kernel::KernelSignal, PollHandle, Pollable},
         Gid, Uid,
     },
+    thread::Thread,
     time::clocks::RealTimeCoarseClock,
 };
 
-const DEFAULT_PIPE_BUF_SIZE: usize = 65536;
+pub(super) const DEFAULT_PIPE_BUF_SIZE: usize = 65536;
+
+/// The maximum capacity of a pipe that can be set by an unprivileged user.
+///
+/// This is the default value of `/proc/sys/fs/pipe-max-size` on Linux.
+pub const PIPE_MAX_SIZE: usize = 1024 * 1024;
 
 pub fn new_pair() -> Result<(Arc<PipeReader>, Arc<PipeWriter>)> {
     let (producer, consumer) = Channel::with_capacity(DEFAULT_PIPE_BUF_SIZE).split();
@@ -36,6 +43,47 @@ pub fn new_pair_with_capacity(capacity: usize) -> Result<(Arc<PipeReader>, Arc<P
     ))
 }
 
+/// Sets the capacity of the pipe that the file belongs to and returns the new capacity.
+///
+/// The requested capacity is rounded up to a power of two that is at least one page. Only
+/// privileged users can set a capacity of more than [`PIPE_MAX_SIZE`] bytes.
+///
+/// # Errors
+///
+/// This function will fail with
+///  - `EBADF` if the file is not an end of a pipe (including a named pipe);
+///  - `EINVAL` if the requested capacity is too large;
+///  - `EPERM` if the user is not privileged to set the requested capacity;
+///  - `EBUSY` if the pipe contains more data than the new capacity.
+pub fn set_pipe_capacity(
+    file: &dyn FileLike,
+    requested: usize,
+    is_privileged: bool,
+) -> Result<usize> {
+    if requested > (1 << 31) {
+        return_errno_with_message!(Errno::EINVAL, "the requested capacity is too large");
+    }
+    let capacity = requested.max(PAGE_SIZE).next_power_of_two();
+    if capacity > PIPE_MAX_SIZE && !is_privileged {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "the requested capacity exceeds the limit for unprivileged users"
+        );
+    }
+
+    file.set_pipe_capacity(capacity)?;
+
+    Ok(capacity)
+}
+
+/// Atomically replaces the status flags in `mask` with those in `new_flags`.
+fn update_status_flags(status_flags: &AtomicU32, mask: StatusFlags, new_flags: StatusFlags) {
+    let new_bits = (new_flags & mask).bits();
+    let _ = status_flags.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+        Some((bits & !mask.bits()) | new_bits)
+    });
+}
+
 pub struct PipeReader {
     consumer: Consumer<u8>,
     status_flags: AtomicU32,
@@ -43,8 +91,6 @@ pub struct PipeReader {
 
 impl PipeReader {
     pub fn new(consumer: Consumer<u8>, status_flags: StatusFlags) -> Result<Arc<Self>> {
-        check_status_flags(status_flags)?;
-
         Ok(Arc::new(Self {
             consumer,
             status_flags: AtomicU32::new(status_flags.bits()),
@@ -73,16 +119,30 @@ impl FileLike for PipeReader {
     }
 
     fn set_status_flags(&self, new_flags: StatusFlags) -> Result<()> {
-        check_status_flags(new_flags)?;
-
+        // TODO: Setting most of the flags will succeed on Linux, but their effects need to be
+        // validated. Currently, only `O_NONBLOCK` and `O_DIRECT` (i.e., the packet mode for
+        // writes) take effect.
         self.status_flags.store(new_flags.bits(), Ordering::Relaxed);
         Ok(())
     }
 
+    fn update_status_flags(&self, mask: StatusFlags, new_flags: StatusFlags) -> Result<()> {
+        update_status_flags(&self.status_flags, mask, new_flags);
+        Ok(())
+    }
+
     fn access_mode(&self) -> AccessMode {
         AccessMode::O_RDONLY
     }
 
+    fn pipe_capacity(&self) -> Result<usize> {
+        Ok(self.consumer.capacity())
+    }
+
+    fn set_pipe_capacity(&self, capacity: usize) -> Result<()> {
+        self.consumer.set_capacity(capacity)
+    }
+
     fn metadata(&self) -> Metadata {
         // This is a dummy implementation.
         // TODO: Add "PipeFS" and link `PipeReader` to it.
@@ -113,8 +173,6 @@ pub struct PipeWriter {
 
 impl PipeWriter {
     pub fn new(producer: Producer<u8>, status_flags: StatusFlags) -> Result<Arc<Self>> {
-        check_status_flags(status_flags)?;
-
         Ok(Arc::new(Self {
             producer,
             status_flags: AtomicU32::new(status_flags.bits()),
@@ -130,11 +188,30 @@ impl Pollable for PipeWriter {
 
 impl FileLike for PipeWriter {
     fn write(&self, reader: &mut VmReader) -> Result<usize> {
-        if self.status_flags().contains(StatusFlags::O_NONBLOCK) {
-            self.producer.try_write(reader)
+        let status_flags = self.status_flags();
+        let mut try_write = || {
+            if status_flags.contains(StatusFlags::O_DIRECT) {
+                self.producer.try_write_packets(reader)
+            } else {
+                self.producer.try_write(reader)
+            }
+        };
+
+        let res = if status_flags.contains(StatusFlags::O_NONBLOCK) {
+            try_write()
         } else {
-            self.wait_events(IoEvents::OUT, None, || self.producer.try_write(reader))
+            self.wait_events(IoEvents::OUT, None, try_write)
+        };
+
+        // "If all file descriptors referring to the read end of a pipe have been closed, then a
+        // write(2) will cause a SIGPIPE signal to be generated for the calling process."
+        //
+        // See <https://man7.org/linux/man-pages/man7/pipe.7.html>.
+        if res.as_ref().is_err_and(|err| err.error() == Errno::EPIPE) {
+            send_sigpipe_to_current();
         }
+
+        res
     }
 
     fn status_flags(&self) -> StatusFlags {
@@ -142,16 +219,30 @@ impl FileLike for PipeWriter {
     }
 
     fn set_status_flags(&self, new_flags: StatusFlags) -> Result<()> {
-        check_status_flags(new_flags)?;
-
+        // TODO: Setting most of the flags will succeed on Linux, but their effects need to be
+        // validated. Currently, only `O_NONBLOCK` and `O_DIRECT` (i.e., the packet mode for
+        // writes) take effect.
         self.status_flags.store(new_flags.bits(), Ordering::Relaxed);
         Ok(())
     }
 
+    fn update_status_flags(&self, mask: StatusFlags, new_flags: StatusFlags) -> Result<()> {
+        update_status_flags(&self.status_flags, mask, new_flags);
+        Ok(())
+    }
+
     fn access_mode(&self) -> AccessMode {
         AccessMode::O_WRONLY
     }
 
+    fn pipe_capacity(&self) -> Result<usize> {
+        Ok(self.producer.capacity())
+    }
+
+    fn set_pipe_capacity(&self, capacity: usize) -> Result<()> {
+        self.producer.set_capacity(capacity)
+    }
+
     fn metadata(&self) -> Metadata {
         // This is a dummy implementation.
         // TODO: Add "PipeFS" and link `PipeWriter` to it.
@@ -175,19 +266,14 @@ impl FileLike for PipeWriter {
     }
 }
 
-fn check_status_flags(status_flags: StatusFlags) -> Result<()> {
-    if status_flags.contains(StatusFlags::O_DIRECT) {
-        // "O_DIRECT .. Older kernels that do not support this flag will indicate this via an
-        // EINVAL error."
-        //
-        // See <https://man7.org/linux/man-pages/man2/pipe.2.html>.
-        return_errno_with_message!(Errno::EINVAL, "the `O_DIRECT` flag is not supported");
+pub(super) fn send_sigpipe_to_current() {
+    // Kernel threads (e.g., in unit tests) do not receive signals.
+    let Some(thread) = Thread::current() else {
+        return;
+    };
+    if let Some(posix_thread) = thread.as_posix_thread() {
+        posix_thread.enqueue_signal(Box::new(KernelSignal::new(SIGPIPE)));
     }
-
-    // TODO: Setting most of the other flags will succeed on Linux, but their effects need to be
-    // validated.
-
-    Ok(())
 }
 
 #[cfg(ktest)]
@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! This module offers `/proc/coverage` file support, which tells the fuzzer
+//! how much of the kernel code has been executed.
+//!
+//! The file contains the number of the covered edges and the number of all
+//! the instrumented edges, separated by a space. Both are zero if the kernel
+//! is not built for fuzzing. See [`ostd::coverage`] for details.
+//!
+//! This file does not exist in Linux.
+
+use alloc::format;
+
+use ostd::coverage;
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    prelude::*,
+};
+
+/// Represents the inode at `/proc/coverage`.
+pub struct CoverageFileOps;
+
+impl CoverageFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self).parent(parent).build().unwrap()
+    }
+}
+
+impl FileOps for CoverageFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let output = format!(
+            "{} {}\n",
+            coverage::nr_covered_edges(),
+            coverage::nr_edges()
+        );
+        Ok(output.into_bytes())
+    }
+}
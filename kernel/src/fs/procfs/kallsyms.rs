@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! This module offers `/proc/kallsyms` file support, which lists the symbols
+//! in the kernel for the profilers and the debuggers in the guest.
+//!
+//! Each line is in the form of `ADDRESS TYPE NAME`. The symbol names are
+//! demangled. Like Linux with `kptr_restrict` set to one, the addresses are
+//! shown as zeros unless the reader has the `CAP_SYSLOG` capability.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man5/proc_kallsyms.5.html>
+
+use core::fmt::Write;
+
+use ostd::kallsyms;
+
+use crate::{
+    fs::{
+        procfs::template::{ProcFileBuilder, SeqOps},
+        utils::Inode,
+    },
+    prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
+};
+
+/// Represents the inode at `/proc/kallsyms`.
+pub struct KallsymsFileOps;
+
+impl KallsymsFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self).parent(parent).build().unwrap()
+    }
+}
+
+impl SeqOps for KallsymsFileOps {
+    fn show(&self, pos: usize, buf: &mut Vec<u8>) -> Result<usize> {
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        let show_addr = credentials.has_capability(CapSet::SYSLOG);
+
+        let mut next_pos = pos;
+        let mut line = String::new();
+        for symbol in kallsyms::iter_from(pos) {
+            line.clear();
+            writeln!(
+                line,
+                "{:016x} {} {}",
+                if show_addr { symbol.addr() } else { 0 },
+                symbol.type_(),
+                symbol.name()
+            )
+            .unwrap();
+            buf.extend_from_slice(line.as_bytes());
+            next_pos += 1;
+
+            // Show a page of symbols at a time, since there are tens of thousands of them.
+            if buf.len() >= PAGE_SIZE {
+                break;
+            }
+        }
+        Ok(next_pos)
+    }
+}
@@ -3,6 +3,10 @@
 //! This module offers `/proc/loadavg` file support, which tells the user space
 //! about the cpu load average for the last 1, 5, and 15 minutes.
 //!
+//! The load is the number of the threads that are runnable, which are either
+//! running or waiting in the run queues. The idle threads of the CPUs are not
+//! counted.
+//!
 //! Reference: <https://www.man7.org/linux/man-pages/man5/proc_loadavg.5.html>
 
 use alloc::format;
@@ -13,7 +17,7 @@ use crate::{
         utils::Inode,
     },
     prelude::*,
-    process::posix_thread,
+    process::{posix_thread, process_table},
     sched::{self, loadavg::get_loadavg},
 };
 
@@ -36,8 +40,8 @@ impl FileOps for LoadAvgFileOps {
             avg[0],
             avg[1],
             avg[2],
-            nr_running,
-            nr_queued,
+            nr_queued + nr_running,
+            process_table::nr_threads(),
             posix_thread::last_tid(),
         );
 
@@ -11,9 +11,10 @@ use alloc::format;
 use crate::{
     fs::{
         procfs::template::{FileOps, ProcFileBuilder},
-        utils::Inode,
+        utils::{nr_dirty_pages, Inode},
     },
     prelude::*,
+    vm::{hugetlb, overcommit},
 };
 
 /// Represents the inode at `/proc/meminfo`.
@@ -32,14 +33,46 @@ impl FileOps for MemInfoFileOps {
         // An estimation of how much memory is available for starting new
         // applications, without disk operations.
         let available = osdk_frame_allocator::load_total_free_size();
+        // The memory that waits to be written back to the devices.
+        let dirty = nr_dirty_pages() * PAGE_SIZE;
+        // The memory that can be committed when the overcommit is disabled.
+        let commit_limit = overcommit::commit_limit();
+        // The memory that has been committed by the private writable mappings.
+        let committed_as = overcommit::committed_as();
+        // The huge pages of the default size.
+        let huge_pool = hugetlb::default_pool();
+        // The memory of the huge pages of all sizes.
+        let hugetlb = hugetlb::pools()
+            .iter()
+            .map(|pool| pool.nr_pages() * pool.page_size())
+            .sum::<usize>();
 
         // Convert the values to KiB.
         let total = total / 1024;
         let available = available / 1024;
         let free = total - available;
+        let dirty = dirty / 1024;
+        let commit_limit = commit_limit / 1024;
+        let committed_as = committed_as / 1024;
+        let hugetlb = hugetlb / 1024;
         let output = format!(
-            "MemTotal:\t{} kB\nMemFree:\t{} kB\nMemAvailable:\t{} kB\n",
-            total, free, available
+            "MemTotal:\t{} kB\nMemFree:\t{} kB\nMemAvailable:\t{} kB\nDirty:\t\t{} kB\n\
+             CommitLimit:\t{} kB\nCommitted_AS:\t{} kB\nHugePages_Total:\t{}\n\
+             HugePages_Free:\t{}\nHugePages_Rsvd:\t{}\nHugePages_Surp:\t{}\n\
+             Hugepagesize:\t{} kB\nHugetlb:\t{} kB\n",
+            total,
+            free,
+            available,
+            dirty,
+            commit_limit,
+            committed_as,
+            huge_pool.nr_pages(),
+            huge_pool.nr_free_pages(),
+            huge_pool.nr_reserved_pages(),
+            // The pool never grows beyond `nr_hugepages`.
+            0,
+            huge_pool.page_size() / 1024,
+            hugetlb
         );
         Ok(output.into_bytes())
     }
@@ -4,15 +4,23 @@ use core::sync::atomic::{AtomicU64, Ordering};
 
 use filesystems::{FileSystemType, FILESYSTEM_TYPES};
 
+pub use self::template::ProcPollEvent;
 use self::{
+    cmdline::CmdlineFileOps,
+    coverage::CoverageFileOps,
     cpuinfo::CpuInfoFileOps,
+    kallsyms::KallsymsFileOps,
     loadavg::LoadAvgFileOps,
     meminfo::MemInfoFileOps,
     pid::PidDirOps,
+    schedstat::SchedStatFileOps,
     self_::SelfSymOps,
+    stat::StatFileOps,
     sys::SysDirOps,
     template::{DirOps, ProcDir, ProcDirBuilder, ProcSymBuilder, SymOps},
     thread_self::ThreadSelfSymOps,
+    uptime::UptimeFileOps,
+    vmstat::VmStatFileOps,
 };
 use crate::{
     events::Observer,
@@ -27,22 +35,33 @@ use crate::{
     },
 };
 
+mod cmdline;
+mod coverage;
 mod cpuinfo;
 mod filesystems;
+mod kallsyms;
 mod loadavg;
 mod meminfo;
 mod pid;
+mod schedstat;
 mod self_;
+mod stat;
 mod sys;
 mod template;
 mod thread_self;
+mod uptime;
+mod vmstat;
 
 pub(super) fn init() {
     FILESYSTEM_TYPES.call_once(|| {
         vec![
             FileSystemType::new("proc", true),
             FileSystemType::new("ramfs", true),
+            FileSystemType::new("tmpfs", true),
+            FileSystemType::new("hugetlbfs", true),
             FileSystemType::new("devpts", true),
+            FileSystemType::new("cgroup2", true),
+            FileSystemType::new("tracefs", true),
             FileSystemType::new("ext2", false),
             FileSystemType::new("exfat", false),
         ]
@@ -132,8 +151,22 @@ impl DirOps for RootDirOps {
             MemInfoFileOps::new_inode(this_ptr.clone())
         } else if name == "loadavg" {
             LoadAvgFileOps::new_inode(this_ptr.clone())
+        } else if name == "cmdline" {
+            CmdlineFileOps::new_inode(this_ptr.clone())
+        } else if name == "coverage" {
+            CoverageFileOps::new_inode(this_ptr.clone())
         } else if name == "cpuinfo" {
             CpuInfoFileOps::new_inode(this_ptr.clone())
+        } else if name == "kallsyms" {
+            KallsymsFileOps::new_inode(this_ptr.clone())
+        } else if name == "schedstat" {
+            SchedStatFileOps::new_inode(this_ptr.clone())
+        } else if name == "stat" {
+            StatFileOps::new_inode(this_ptr.clone())
+        } else if name == "uptime" {
+            UptimeFileOps::new_inode(this_ptr.clone())
+        } else if name == "vmstat" {
+            VmStatFileOps::new_inode(this_ptr.clone())
         } else if let Ok(pid) = name.parse::<Pid>() {
             let process_ref =
                 process_table::get_process(pid).ok_or_else(|| Error::new(Errno::ENOENT))?;
@@ -162,8 +195,22 @@ impl DirOps for RootDirOps {
             .put_entry_if_not_found("meminfo", || MemInfoFileOps::new_inode(this_ptr.clone()));
         cached_children
             .put_entry_if_not_found("loadavg", || LoadAvgFileOps::new_inode(this_ptr.clone()));
+        cached_children
+            .put_entry_if_not_found("cmdline", || CmdlineFileOps::new_inode(this_ptr.clone()));
+        cached_children
+            .put_entry_if_not_found("coverage", || CoverageFileOps::new_inode(this_ptr.clone()));
         cached_children
             .put_entry_if_not_found("cpuinfo", || CpuInfoFileOps::new_inode(this_ptr.clone()));
+        cached_children
+            .put_entry_if_not_found("kallsyms", || KallsymsFileOps::new_inode(this_ptr.clone()));
+        cached_children.put_entry_if_not_found("schedstat", || {
+            SchedStatFileOps::new_inode(this_ptr.clone())
+        });
+        cached_children.put_entry_if_not_found("stat", || StatFileOps::new_inode(this_ptr.clone()));
+        cached_children
+            .put_entry_if_not_found("uptime", || UptimeFileOps::new_inode(this_ptr.clone()));
+        cached_children
+            .put_entry_if_not_found("vmstat", || VmStatFileOps::new_inode(this_ptr.clone()));
         for process in process_table::process_table_mut().iter() {
             let pid = process.pid().to_string();
             cached_children.put_entry_if_not_found(&pid, || {
@@ -8,7 +8,7 @@ use crate::{
         procfs::{
             pid::FdEvents, DirOps, Observer, ProcDir, ProcDirBuilder, ProcSymBuilder, SymOps,
         },
-        utils::{DirEntryVecExt, Inode},
+        utils::{DirEntryVecExt, Inode, InodeType},
     },
     prelude::*,
     process::posix_thread::AsPosixThread,
@@ -53,24 +53,13 @@ impl Observer<FdEvents> for ProcDir<FdDirOps> {
 
 impl DirOps for FdDirOps {
     fn lookup_child(&self, this_ptr: Weak<dyn Inode>, name: &str) -> Result<Arc<dyn Inode>> {
-        let main_thread = self.0.main_thread();
-        let file_table = main_thread.as_posix_thread().unwrap().file_table().lock();
-        let file_table = file_table
-            .as_ref()
-            .ok_or_else(|| Error::new(Errno::ENOENT))?;
-
-        let file = {
-            let fd = name
-                .parse::<FileDesc>()
-                .map_err(|_| Error::new(Errno::ENOENT))?;
-            file_table
-                .read()
-                .get_file(fd)
-                .map_err(|_| Error::new(Errno::ENOENT))?
-                .clone()
-        };
+        let fd = name
+            .parse::<FileDesc>()
+            .map_err(|_| Error::new(Errno::ENOENT))?;
+        // Check that the file exists.
+        file_of(&self.0, fd)?;
 
-        Ok(FileSymOps::new_inode(file, this_ptr.clone()))
+        Ok(FileSymOps::new_inode(self.0.clone(), fd, this_ptr.clone()))
     }
 
     fn populate_children(&self, this_ptr: Weak<dyn Inode>) {
@@ -86,20 +75,30 @@ impl DirOps for FdDirOps {
         };
         let mut cached_children = this.cached_children().write();
 
-        for (fd, file) in file_table.read().fds_and_files() {
+        for (fd, _) in file_table.read().fds_and_files() {
             cached_children.put_entry_if_not_found(&fd.to_string(), || {
-                FileSymOps::new_inode(file.clone(), this_ptr.clone())
+                FileSymOps::new_inode(self.0.clone(), fd, this_ptr.clone())
             });
         }
     }
 }
 
 /// Represents the inode at `/proc/[pid]/fd/N`.
-struct FileSymOps(Arc<dyn FileLike>);
+///
+/// The target is looked up in the file table on each read, so it is always the file that the
+/// file descriptor currently refers to.
+struct FileSymOps {
+    process: Arc<Process>,
+    fd: FileDesc,
+}
 
 impl FileSymOps {
-    pub fn new_inode(file: Arc<dyn FileLike>, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
-        ProcSymBuilder::new(Self(file))
+    pub fn new_inode(
+        process: Arc<Process>,
+        fd: FileDesc,
+        parent: Weak<dyn Inode>,
+    ) -> Arc<dyn Inode> {
+        ProcSymBuilder::new(Self { process, fd })
             .parent(parent)
             .build()
             .unwrap()
@@ -108,8 +107,14 @@ impl FileSymOps {
 
 impl SymOps for FileSymOps {
     fn read_link(&self) -> Result<String> {
-        let path = if let Some(inode_handle) = self.0.downcast_ref::<InodeHandle>() {
+        let file = file_of(&self.process, self.fd)?;
+
+        let path = if let Some(inode_handle) = file.downcast_ref::<InodeHandle>() {
             inode_handle.dentry().abs_path()
+        } else if file.as_socket().is_some() {
+            format!("socket:[{}]", file.metadata().ino)
+        } else if file.metadata().type_ == InodeType::NamedPipe {
+            format!("pipe:[{}]", file.metadata().ino)
         } else {
             // TODO: get the real path for other FileLike object
             String::from("/dev/tty")
@@ -117,3 +122,18 @@ impl SymOps for FileSymOps {
         Ok(path)
     }
 }
+
+/// Returns the file that the file descriptor of the process refers to.
+fn file_of(process: &Process, fd: FileDesc) -> Result<Arc<dyn FileLike>> {
+    let main_thread = process.main_thread();
+    let file_table = main_thread.as_posix_thread().unwrap().file_table().lock();
+    let file_table = file_table
+        .as_ref()
+        .ok_or_else(|| Error::new(Errno::ENOENT))?;
+
+    file_table
+        .read()
+        .get_file(fd)
+        .map(Arc::clone)
+        .map_err(|_| Error::new(Errno::ENOENT))
+}
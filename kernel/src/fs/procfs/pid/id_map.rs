@@ -0,0 +1,77 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    prelude::*,
+    process::posix_thread::AsPosixThread,
+    Process,
+};
+
+/// Represents the inode at `/proc/[pid]/uid_map` or `/proc/[pid]/gid_map`.
+///
+/// The file shows the uid map or the gid map of the user namespace of the process, and the map
+/// can be written once.
+pub struct IdMapFileOps {
+    process_ref: Arc<Process>,
+    kind: IdKind,
+}
+
+/// The kind of the IDs in a map.
+#[derive(Debug, Clone, Copy)]
+pub enum IdKind {
+    Uid,
+    Gid,
+}
+
+impl IdMapFileOps {
+    pub fn new_inode(
+        process_ref: Arc<Process>,
+        kind: IdKind,
+        parent: Weak<dyn Inode>,
+    ) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self { process_ref, kind })
+            .parent(parent)
+            .writable()
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for IdMapFileOps {
+    /// Reference: <https://elixir.bootlin.com/linux/v6.13/source/kernel/user_namespace.c#L742>
+    fn data(&self) -> Result<Vec<u8>> {
+        let main_thread = self.process_ref.main_thread();
+        let user_ns = main_thread
+            .as_posix_thread()
+            .unwrap()
+            .credentials()
+            .user_ns();
+
+        let output = match self.kind {
+            IdKind::Uid => user_ns.uid_map_to_string(),
+            IdKind::Gid => user_ns.gid_map_to_string(),
+        };
+        Ok(output.into_bytes())
+    }
+
+    /// Reference: <https://elixir.bootlin.com/linux/v6.13/source/kernel/user_namespace.c#L907>
+    fn write_data(&self, data: &[u8]) -> Result<()> {
+        let main_thread = self.process_ref.main_thread();
+        let user_ns = main_thread
+            .as_posix_thread()
+            .unwrap()
+            .credentials()
+            .user_ns();
+        let writer = current_thread!().as_posix_thread().unwrap().credentials();
+
+        let data = core::str::from_utf8(data)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the data is not valid UTF-8"))?;
+        match self.kind {
+            IdKind::Uid => user_ns.write_uid_map(data, &writer),
+            IdKind::Gid => user_ns.write_gid_map(data, &writer),
+        }
+    }
+}
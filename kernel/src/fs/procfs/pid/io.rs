@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::fmt::Write;
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    prelude::*,
+    Process,
+};
+
+/// Represents the inode at `/proc/[pid]/io`.
+pub struct IoFileOps(Arc<Process>);
+
+impl IoFileOps {
+    pub fn new_inode(process_ref: Arc<Process>, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self(process_ref))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for IoFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let io_stats = self.0.io_stats();
+
+        let mut output = String::new();
+        writeln!(output, "rchar: {}", io_stats.rchar()).unwrap();
+        writeln!(output, "wchar: {}", io_stats.wchar()).unwrap();
+        writeln!(output, "syscr: {}", io_stats.syscr()).unwrap();
+        writeln!(output, "syscw: {}", io_stats.syscw()).unwrap();
+        writeln!(output, "read_bytes: {}", io_stats.read_bytes()).unwrap();
+        writeln!(output, "write_bytes: {}", io_stats.write_bytes()).unwrap();
+        writeln!(
+            output,
+            "cancelled_write_bytes: {}",
+            io_stats.cancelled_write_bytes()
+        )
+        .unwrap();
+        Ok(output.into_bytes())
+    }
+}
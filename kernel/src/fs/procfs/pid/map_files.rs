@@ -0,0 +1,149 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::ops::Range;
+
+use crate::{
+    fs::{
+        path::Dentry,
+        procfs::{DirOps, ProcDir, ProcDirBuilder, ProcSymBuilder, SymOps},
+        utils::{DirEntryVecExt, Inode},
+    },
+    prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
+    vm::vmar::VmMappingInfo,
+    Process,
+};
+
+/// Represents the inode at `/proc/[pid]/map_files`.
+///
+/// Each entry is a symbolic link named by the address range of a file-backed mapping, i.e.,
+/// `start-end` in hexadecimal, which links to the mapped file.
+pub struct MapFilesDirOps(Arc<Process>);
+
+impl MapFilesDirOps {
+    pub fn new_inode(process_ref: Arc<Process>, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcDirBuilder::new(Self(process_ref))
+            .parent(parent)
+            // The mappings change over time, so the entries must not be cached.
+            .volatile()
+            .build()
+            .unwrap()
+    }
+}
+
+impl DirOps for MapFilesDirOps {
+    fn lookup_child(&self, this_ptr: Weak<dyn Inode>, name: &str) -> Result<Arc<dyn Inode>> {
+        let range = parse_range(name).ok_or_else(|| Error::new(Errno::ENOENT))?;
+        // Check that the mapping exists.
+        mapped_file_of(&self.0, &range)?;
+
+        Ok(MapFileSymOps::new_inode(self.0.clone(), range, this_ptr))
+    }
+
+    fn populate_children(&self, this_ptr: Weak<dyn Inode>) {
+        let names = file_mappings_of(&self.0)
+            .map(|info| format_range(&info.range))
+            .collect::<Vec<_>>();
+
+        let this = {
+            let this = this_ptr.upgrade().unwrap();
+            this.downcast_ref::<ProcDir<MapFilesDirOps>>()
+                .unwrap()
+                .this()
+        };
+        let mut cached_children = this.cached_children().write();
+
+        // Remove the entries of the mappings that have gone.
+        let stale_names = cached_children
+            .iter()
+            .map(|(name, _)| name.clone())
+            .filter(|name| !names.contains(name))
+            .collect::<Vec<_>>();
+        for name in stale_names {
+            cached_children.remove_entry_by_name(&name);
+        }
+
+        for name in names {
+            cached_children.put_entry_if_not_found(&name, || {
+                let range = parse_range(&name).unwrap();
+                MapFileSymOps::new_inode(self.0.clone(), range, this_ptr.clone())
+            });
+        }
+    }
+}
+
+/// Represents the inode at `/proc/[pid]/map_files/[start]-[end]`.
+struct MapFileSymOps {
+    process: Arc<Process>,
+    range: Range<Vaddr>,
+}
+
+impl MapFileSymOps {
+    pub fn new_inode(
+        process: Arc<Process>,
+        range: Range<Vaddr>,
+        parent: Weak<dyn Inode>,
+    ) -> Arc<dyn Inode> {
+        ProcSymBuilder::new(Self { process, range })
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl SymOps for MapFileSymOps {
+    fn read_link(&self) -> Result<String> {
+        // Like Linux, the links can only be followed by checkpoint/restore tools, because they
+        // give access to the files that may be no longer reachable otherwise.
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        if !credentials.has_capability(CapSet::SYS_ADMIN)
+            && !credentials.has_capability(CapSet::CHECKPOINT_RESTORE)
+        {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "reading the mapped files requires the CAP_CHECKPOINT_RESTORE capability"
+            );
+        }
+
+        let mapped_file = mapped_file_of(&self.process, &self.range)?;
+        Ok(mapped_file.abs_path())
+    }
+}
+
+/// Returns the file-backed mappings of the process.
+fn file_mappings_of(process: &Process) -> impl Iterator<Item = VmMappingInfo> {
+    let vmar = process.lock_root_vmar();
+    // A zombie process has no mappings.
+    let mappings_info = vmar
+        .get()
+        .map(|vmar| vmar.mappings_info())
+        .unwrap_or_default();
+
+    mappings_info
+        .into_iter()
+        .filter(|info| info.mapped_file.is_some())
+}
+
+/// Returns the file mapped in the address range of the process.
+fn mapped_file_of(process: &Process, range: &Range<Vaddr>) -> Result<Dentry> {
+    file_mappings_of(process)
+        .find(|info| info.range == *range)
+        .and_then(|info| info.mapped_file)
+        .ok_or_else(|| Error::new(Errno::ENOENT))
+}
+
+fn format_range(range: &Range<Vaddr>) -> String {
+    format!("{:x}-{:x}", range.start, range.end)
+}
+
+/// Parses a name in the form of `start-end`.
+///
+/// Like Linux, the name must be in the canonical form, e.g., without leading zeros.
+fn parse_range(name: &str) -> Option<Range<Vaddr>> {
+    let (start, end) = name.split_once('-')?;
+    let start = Vaddr::from_str_radix(start, 16).ok()?;
+    let end = Vaddr::from_str_radix(end, 16).ok()?;
+    let range = start..end;
+
+    (format_range(&range) == name).then_some(range)
+}
@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::fmt::Write;
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    prelude::*,
+    vm::perms::VmPerms,
+    Process,
+};
+
+/// Represents the inode at `/proc/[pid]/maps`.
+/// See https://github.com/torvalds/linux/blob/ce1c54fdff7c4556b08f5b875a331d8952e8b6b7/fs/proc/task_mmu.c#L304
+/// FIXME: The device, the inode number and the path name of file-backed mappings, and the names
+/// of special mappings (e.g., `[heap]` and `[stack]`) are not reported yet.
+///
+/// Each line describes a mapping with the following fields:
+/// - address: The start and end addresses of the mapping.
+/// - perms:   The permissions (`r`, `w`, `x`) and whether the mapping is shared (`s`) or
+///   private (`p`).
+/// - offset:  The offset in the mapped file.
+/// - dev:     The device (major:minor) of the mapped file.
+/// - inode:   The inode number of the mapped file.
+pub struct MapsFileOps(Arc<Process>);
+
+impl MapsFileOps {
+    pub fn new_inode(process_ref: Arc<Process>, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self(process_ref))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for MapsFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let mappings_info = {
+            let vmar = self.0.lock_root_vmar();
+            // A zombie process has no mappings.
+            let Some(vmar) = vmar.get() else {
+                return Ok(Vec::new());
+            };
+            vmar.mappings_info()
+        };
+
+        let mut maps_output = String::new();
+        for info in mappings_info {
+            let perm = |perm: VmPerms, c: char| if info.perms.contains(perm) { c } else { '-' };
+            writeln!(
+                maps_output,
+                "{:08x}-{:08x} {}{}{}{} {:08x} 00:00 0 ",
+                info.range.start,
+                info.range.end,
+                perm(VmPerms::READ, 'r'),
+                perm(VmPerms::WRITE, 'w'),
+                perm(VmPerms::EXEC, 'x'),
+                if info.is_shared { 's' } else { 'p' },
+                info.vmo_offset,
+            )
+            .unwrap();
+        }
+        Ok(maps_output.into_bytes())
+    }
+}
@@ -1,7 +1,20 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use self::{
-    cmdline::CmdlineFileOps, comm::CommFileOps, exe::ExeSymOps, fd::FdDirOps, task::TaskDirOps,
+    cgroup::CgroupFileOps,
+    cmdline::CmdlineFileOps,
+    comm::CommFileOps,
+    exe::ExeSymOps,
+    fd::FdDirOps,
+    id_map::{IdKind, IdMapFileOps},
+    io::IoFileOps,
+    map_files::MapFilesDirOps,
+    maps::MapsFileOps,
+    schedstat::SchedStatFileOps,
+    setgroups::SetgroupsFileOps,
+    smaps_rollup::SmapsRollupFileOps,
+    task::TaskDirOps,
+    timens_offsets::TimensOffsetsFileOps,
 };
 use super::template::{DirOps, ProcDir, ProcDirBuilder};
 use crate::{
@@ -14,13 +27,22 @@ use crate::{
     process::{posix_thread::AsPosixThread, Process},
 };
 
+mod cgroup;
 mod cmdline;
 mod comm;
 mod exe;
 mod fd;
+mod id_map;
+mod io;
+mod map_files;
+mod maps;
+mod schedstat;
+mod setgroups;
+mod smaps_rollup;
 mod stat;
 mod status;
 mod task;
+mod timens_offsets;
 
 /// Represents the inode at `/proc/[pid]`.
 pub struct PidDirOps(Arc<Process>);
@@ -62,10 +84,20 @@ impl DirOps for PidDirOps {
             "exe" => ExeSymOps::new_inode(self.0.clone(), this_ptr.clone()),
             "comm" => CommFileOps::new_inode(self.0.clone(), this_ptr.clone()),
             "fd" => FdDirOps::new_inode(self.0.clone(), this_ptr.clone()),
+            "gid_map" => IdMapFileOps::new_inode(self.0.clone(), IdKind::Gid, this_ptr.clone()),
+            "cgroup" => CgroupFileOps::new_inode(self.0.clone(), this_ptr.clone()),
             "cmdline" => CmdlineFileOps::new_inode(self.0.clone(), this_ptr.clone()),
+            "io" => IoFileOps::new_inode(self.0.clone(), this_ptr.clone()),
+            "map_files" => MapFilesDirOps::new_inode(self.0.clone(), this_ptr.clone()),
+            "maps" => MapsFileOps::new_inode(self.0.clone(), this_ptr.clone()),
+            "schedstat" => SchedStatFileOps::new_inode(self.0.clone(), this_ptr.clone()),
+            "setgroups" => SetgroupsFileOps::new_inode(self.0.clone(), this_ptr.clone()),
+            "smaps_rollup" => SmapsRollupFileOps::new_inode(self.0.clone(), this_ptr.clone()),
             "status" => status::StatusFileOps::new_inode(self.0.clone(), this_ptr.clone()),
             "stat" => stat::StatFileOps::new_inode(self.0.clone(), this_ptr.clone()),
             "task" => TaskDirOps::new_inode(self.0.clone(), this_ptr.clone()),
+            "timens_offsets" => TimensOffsetsFileOps::new_inode(self.0.clone(), this_ptr.clone()),
+            "uid_map" => IdMapFileOps::new_inode(self.0.clone(), IdKind::Uid, this_ptr.clone()),
             _ => return_errno!(Errno::ENOENT),
         };
         Ok(inode)
@@ -86,9 +118,33 @@ impl DirOps for PidDirOps {
         cached_children.put_entry_if_not_found("fd", || {
             FdDirOps::new_inode(self.0.clone(), this_ptr.clone())
         });
+        cached_children.put_entry_if_not_found("gid_map", || {
+            IdMapFileOps::new_inode(self.0.clone(), IdKind::Gid, this_ptr.clone())
+        });
+        cached_children.put_entry_if_not_found("cgroup", || {
+            CgroupFileOps::new_inode(self.0.clone(), this_ptr.clone())
+        });
         cached_children.put_entry_if_not_found("cmdline", || {
             CmdlineFileOps::new_inode(self.0.clone(), this_ptr.clone())
         });
+        cached_children.put_entry_if_not_found("io", || {
+            IoFileOps::new_inode(self.0.clone(), this_ptr.clone())
+        });
+        cached_children.put_entry_if_not_found("map_files", || {
+            MapFilesDirOps::new_inode(self.0.clone(), this_ptr.clone())
+        });
+        cached_children.put_entry_if_not_found("maps", || {
+            MapsFileOps::new_inode(self.0.clone(), this_ptr.clone())
+        });
+        cached_children.put_entry_if_not_found("schedstat", || {
+            SchedStatFileOps::new_inode(self.0.clone(), this_ptr.clone())
+        });
+        cached_children.put_entry_if_not_found("setgroups", || {
+            SetgroupsFileOps::new_inode(self.0.clone(), this_ptr.clone())
+        });
+        cached_children.put_entry_if_not_found("smaps_rollup", || {
+            SmapsRollupFileOps::new_inode(self.0.clone(), this_ptr.clone())
+        });
         cached_children.put_entry_if_not_found("status", || {
             status::StatusFileOps::new_inode(self.0.clone(), this_ptr.clone())
         });
@@ -98,5 +154,11 @@ impl DirOps for PidDirOps {
         cached_children.put_entry_if_not_found("task", || {
             TaskDirOps::new_inode(self.0.clone(), this_ptr.clone())
         });
+        cached_children.put_entry_if_not_found("timens_offsets", || {
+            TimensOffsetsFileOps::new_inode(self.0.clone(), this_ptr.clone())
+        });
+        cached_children.put_entry_if_not_found("uid_map", || {
+            IdMapFileOps::new_inode(self.0.clone(), IdKind::Uid, this_ptr.clone())
+        });
     }
 }
@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::fmt::Write;
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    prelude::*,
+    Process,
+};
+
+/// Represents the inode at `/proc/[pid]/schedstat`.
+///
+/// The file contains the time that the main thread runs (ns), the time that it
+/// waits in the run queues (ns), and the number of its time slices.
+pub struct SchedStatFileOps(Arc<Process>);
+
+impl SchedStatFileOps {
+    pub fn new_inode(process_ref: Arc<Process>, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self(process_ref))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for SchedStatFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let stat = self.0.main_thread().sched_attr().sched_stat();
+
+        let mut output = String::new();
+        writeln!(
+            output,
+            "{} {} {}",
+            stat.run_time_ns, stat.run_delay_ns, stat.nr_slices
+        )
+        .unwrap();
+        Ok(output.into_bytes())
+    }
+}
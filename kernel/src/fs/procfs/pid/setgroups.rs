@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
+    Process,
+};
+
+/// Represents the inode at `/proc/[pid]/setgroups`.
+///
+/// The file shows whether `setgroups` is allowed in the user namespace of the process, i.e.,
+/// `allow` or `deny`. It can be set to `deny` before the gid map is written, so that an
+/// unprivileged user can map its own group.
+pub struct SetgroupsFileOps(Arc<Process>);
+
+impl SetgroupsFileOps {
+    pub fn new_inode(process_ref: Arc<Process>, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self(process_ref))
+            .parent(parent)
+            .writable()
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for SetgroupsFileOps {
+    /// Reference: <https://elixir.bootlin.com/linux/v6.13/source/kernel/user_namespace.c#L1240>
+    fn data(&self) -> Result<Vec<u8>> {
+        let main_thread = self.0.main_thread();
+        let user_ns = main_thread
+            .as_posix_thread()
+            .unwrap()
+            .credentials()
+            .user_ns();
+
+        let output = if user_ns.is_setgroups_allowed() {
+            "allow\n"
+        } else {
+            "deny\n"
+        };
+        Ok(output.as_bytes().to_vec())
+    }
+
+    /// Reference: <https://elixir.bootlin.com/linux/v6.13/source/kernel/user_namespace.c#L1254>
+    fn write_data(&self, data: &[u8]) -> Result<()> {
+        let main_thread = self.0.main_thread();
+        let user_ns = main_thread
+            .as_posix_thread()
+            .unwrap()
+            .credentials()
+            .user_ns();
+
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        if !credentials.has_capability_in(CapSet::SYS_ADMIN, &user_ns) {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "changing setgroups requires CAP_SYS_ADMIN in the user namespace"
+            );
+        }
+
+        let is_allowed = match data.trim_ascii() {
+            b"allow" => true,
+            b"deny" => false,
+            _ => return_errno_with_message!(Errno::EINVAL, "the value must be allow or deny"),
+        };
+        user_ns.set_setgroups_allowed(is_allowed)
+    }
+}
@@ -0,0 +1,75 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::fmt::Write;
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    prelude::*,
+    Process,
+};
+
+/// Represents the inode at `/proc/[pid]/smaps_rollup`.
+/// See https://docs.kernel.org/filesystems/proc.html#proc-pid-smaps-rollup
+///
+/// The first line covers the range of all the mappings, followed by the memory usage of all the
+/// mappings in kB. Only a subset of the Linux fields is supported.
+pub struct SmapsRollupFileOps(Arc<Process>);
+
+impl SmapsRollupFileOps {
+    pub fn new_inode(process_ref: Arc<Process>, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self(process_ref))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for SmapsRollupFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let (mappings_info, usage) = {
+            let vmar = self.0.lock_root_vmar();
+            // A zombie process has no mappings.
+            let Some(vmar) = vmar.get() else {
+                return Ok(Vec::new());
+            };
+            (vmar.mappings_info(), vmar.memory_usage())
+        };
+        let (Some(first), Some(last)) = (mappings_info.first(), mappings_info.last()) else {
+            return Ok(Vec::new());
+        };
+
+        let mut output = String::new();
+        writeln!(
+            output,
+            "{:08x}-{:08x} ---p 00000000 00:00 0 {:>26}[rollup]",
+            first.range.start, last.range.end, ""
+        )
+        .unwrap();
+        let fields = [
+            ("Rss", usage.rss),
+            ("Pss", usage.pss),
+            ("Pss_Anon", usage.pss_anon),
+            ("Pss_File", usage.pss_file),
+            ("Pss_Shmem", usage.pss_shmem),
+            ("Shared_Clean", usage.shared_clean),
+            ("Shared_Dirty", usage.shared_dirty),
+            ("Private_Clean", usage.private_clean),
+            ("Private_Dirty", usage.private_dirty),
+            ("Referenced", usage.referenced),
+            ("Anonymous", usage.anonymous),
+        ];
+        for (name, bytes) in fields {
+            writeln!(
+                output,
+                "{:<16}{:>8} kB",
+                format_args!("{}:", name),
+                bytes / 1024
+            )
+            .unwrap();
+        }
+        Ok(output.into_bytes())
+    }
+}
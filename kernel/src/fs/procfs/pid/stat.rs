@@ -8,6 +8,7 @@ use crate::{
         utils::Inode,
     },
     prelude::*,
+    process::posix_thread::AsPosixThread,
     Process,
 };
 
@@ -19,7 +20,7 @@ use crate::{
 /// Fields:
 /// - pid              : Process ID.
 /// - comm             : Process name.
-/// - state            : Process state (R: running, S: sleeping, Z: zombie).
+/// - state            : Process state (R: running, S: sleeping, D: disk sleep, T: stopped, Z: zombie).
 /// - ppid             : Parent process ID.
 /// - pgrp             : Process group ID.
 /// - session          : Session ID.
@@ -85,11 +86,7 @@ impl FileOps for StatFileOps {
 
         let pid = process.pid();
         let comm = process.executable_path();
-        let state = if process.status().is_zombie() {
-            'Z'
-        } else {
-            'R'
-        };
+        let (state, _) = process_state(process);
         let ppid = process.parent().pid();
         let pgrp = process.pgid();
 
@@ -103,3 +100,27 @@ impl FileOps for StatFileOps {
         Ok(stat_output.into_bytes())
     }
 }
+
+/// Returns the state of the process in the form of `ps`, along with its description.
+///
+/// The state of a process is the state of its main thread. A thread sleeps in the `S` state if it
+/// can be interrupted by signals, or in the `D` state otherwise.
+pub(super) fn process_state(process: &Process) -> (char, &'static str) {
+    if process.status().is_zombie() {
+        return ('Z', "zombie");
+    }
+
+    let main_thread = process.main_thread();
+    if main_thread.is_stopped() {
+        ('T', "stopped")
+    } else if !main_thread.is_sleeping() {
+        ('R', "running")
+    } else if main_thread
+        .as_posix_thread()
+        .is_some_and(|posix_thread| posix_thread.is_interruptible())
+    {
+        ('S', "sleeping")
+    } else {
+        ('D', "disk sleep")
+    }
+}
@@ -2,6 +2,7 @@
 
 use core::fmt::Write;
 
+use super::stat::process_state;
 use crate::{
     fs::{
         procfs::template::{FileOps, ProcFileBuilder},
@@ -18,7 +19,8 @@ use crate::{
 ///
 /// Fields:
 /// - Name:   The name of the process.
-/// - State:  The current state of the process (e.g., R for running, S for sleeping).
+/// - State:  The current state of the process (e.g., R for running, S for sleeping, D for
+///   uninterruptible sleep).
 /// - Tgid:   The Thread Group ID, which is the same as the process ID for the main thread.
 /// - Pid:    The process ID.
 /// - PPid:   The parent process ID.
@@ -51,6 +53,7 @@ use crate::{
 /// - CapEff: Effective capabilities.
 /// - CapBnd: Bounding set.
 /// - CapAmb: Ambient capabilities.
+/// - NoNewPrivs: Whether the process cannot gain privileges via `execve`.
 /// - Seccomp: Seccomp mode.
 /// - Cpus_allowed: CPUs allowed for this process.
 /// - Cpus_allowed_list: List of CPUs allowed for this process.
@@ -73,14 +76,43 @@ impl FileOps for StatusFileOps {
     fn data(&self) -> Result<Vec<u8>> {
         let process = &self.0;
         let main_thread = process.main_thread();
-        let file_table = main_thread.as_posix_thread().unwrap().file_table();
+        let posix_thread = main_thread.as_posix_thread().unwrap();
+        let file_table = posix_thread.file_table();
+        let credentials = posix_thread.credentials();
+        // The IDs are seen from the user namespace of the reader.
+        let user_ns = current_thread!()
+            .as_posix_thread()
+            .unwrap()
+            .credentials()
+            .user_ns();
 
         let mut status_output = String::new();
         writeln!(status_output, "Name:\t{}", process.executable_path()).unwrap();
+        let (state, state_desc) = process_state(process);
+        writeln!(status_output, "State:\t{} ({})", state, state_desc).unwrap();
         writeln!(status_output, "Tgid:\t{}", process.pid()).unwrap();
         writeln!(status_output, "Pid:\t{}", process.pid()).unwrap();
         writeln!(status_output, "PPid:\t{}", process.parent().pid()).unwrap();
-        writeln!(status_output, "TracerPid:\t{}", process.parent().pid()).unwrap(); // Assuming TracerPid is the same as PPid
+        // Tracing is not supported, so there is never a tracer.
+        writeln!(status_output, "TracerPid:\t0").unwrap();
+        writeln!(
+            status_output,
+            "Uid:\t{}\t{}\t{}\t{}",
+            user_ns.uid_from_kernel_or_overflow(credentials.ruid()),
+            user_ns.uid_from_kernel_or_overflow(credentials.euid()),
+            user_ns.uid_from_kernel_or_overflow(credentials.suid()),
+            user_ns.uid_from_kernel_or_overflow(credentials.fsuid())
+        )
+        .unwrap();
+        writeln!(
+            status_output,
+            "Gid:\t{}\t{}\t{}\t{}",
+            user_ns.gid_from_kernel_or_overflow(credentials.rgid()),
+            user_ns.gid_from_kernel_or_overflow(credentials.egid()),
+            user_ns.gid_from_kernel_or_overflow(credentials.sgid()),
+            user_ns.gid_from_kernel_or_overflow(credentials.fsgid())
+        )
+        .unwrap();
         writeln!(
             status_output,
             "FDSize:\t{}",
@@ -91,12 +123,40 @@ impl FileOps for StatusFileOps {
                 .unwrap_or(0)
         )
         .unwrap();
+        write!(status_output, "Groups:\t").unwrap();
+        for gid in credentials.groups().iter() {
+            write!(
+                status_output,
+                "{} ",
+                user_ns.gid_from_kernel_or_overflow(*gid)
+            )
+            .unwrap();
+        }
+        writeln!(status_output).unwrap();
+        // A zombie process has no mappings.
+        let vm_size = process
+            .lock_root_vmar()
+            .get()
+            .map_or(0, |vmar| vmar.total_vm());
+        writeln!(status_output, "VmSize:\t{:>8} kB", vm_size / 1024).unwrap();
         writeln!(
             status_output,
             "Threads:\t{}",
             process.tasks().lock().as_slice().len()
         )
         .unwrap();
+        writeln!(
+            status_output,
+            "NoNewPrivs:\t{}",
+            posix_thread.no_new_privs() as u8
+        )
+        .unwrap();
+        writeln!(
+            status_output,
+            "Seccomp:\t{}",
+            posix_thread.seccomp().mode() as u8
+        )
+        .unwrap();
         Ok(status_output.into_bytes())
     }
 }
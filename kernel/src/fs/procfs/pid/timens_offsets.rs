@@ -0,0 +1,102 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::fmt::Write;
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
+    syscall::ClockId,
+    time::clockid_t,
+    Process,
+};
+
+/// Represents the inode at `/proc/[pid]/timens_offsets`.
+///
+/// The file shows the clock offsets of the time namespace that the children of the process
+/// will belong to, and the offsets can be written before any process enters the namespace.
+pub struct TimensOffsetsFileOps(Arc<Process>);
+
+impl TimensOffsetsFileOps {
+    pub fn new_inode(process_ref: Arc<Process>, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self(process_ref))
+            .parent(parent)
+            .writable()
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for TimensOffsetsFileOps {
+    /// Reference: <https://elixir.bootlin.com/linux/v6.13/source/kernel/time/namespace.c#L374>
+    fn data(&self) -> Result<Vec<u8>> {
+        let offsets = self.0.time_ns_for_children().offsets();
+
+        let mut output = String::new();
+        for (name, offset) in [
+            ("monotonic", offsets.monotonic),
+            ("boottime", offsets.boottime),
+        ] {
+            let secs = offset.div_euclid(NSEC_PER_SEC);
+            let nsecs = offset.rem_euclid(NSEC_PER_SEC);
+            writeln!(output, "{:<10} {:>10} {:>9}", name, secs, nsecs).unwrap();
+        }
+
+        Ok(output.into_bytes())
+    }
+
+    /// Reference: <https://elixir.bootlin.com/linux/v6.13/source/fs/proc/base.c#L1660>
+    fn write_data(&self, data: &[u8]) -> Result<()> {
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        if !credentials.has_capability(CapSet::SYS_TIME) {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "setting the clock offsets requires CAP_SYS_TIME"
+            );
+        }
+
+        let data = core::str::from_utf8(data)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the data is not valid UTF-8"))?;
+        let offsets = data
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(parse_offset)
+            .collect::<Result<Vec<_>>>()?;
+
+        self.0.time_ns_for_children().set_offsets(&offsets)
+    }
+}
+
+const NSEC_PER_SEC: i64 = 1_000_000_000;
+
+/// Parses a line of `<clock> <secs> <nsecs>`, where the clock is given by its name or ID.
+fn parse_offset(line: &str) -> Result<(clockid_t, i64)> {
+    let invalid = || Error::with_message(Errno::EINVAL, "the offset is malformed");
+
+    let mut fields = line.split_whitespace();
+    let (Some(clock), Some(secs), Some(nsecs), None) =
+        (fields.next(), fields.next(), fields.next(), fields.next())
+    else {
+        return Err(invalid());
+    };
+
+    let clockid = match clock {
+        "monotonic" => ClockId::CLOCK_MONOTONIC as clockid_t,
+        "boottime" => ClockId::CLOCK_BOOTTIME as clockid_t,
+        _ => clock.parse::<clockid_t>().map_err(|_| invalid())?,
+    };
+    let secs = secs.parse::<i64>().map_err(|_| invalid())?;
+    let nsecs = nsecs.parse::<i64>().map_err(|_| invalid())?;
+    if !(0..NSEC_PER_SEC).contains(&nsecs) {
+        return Err(invalid());
+    }
+
+    let offset = secs
+        .checked_mul(NSEC_PER_SEC)
+        .and_then(|offset| offset.checked_add(nsecs))
+        .ok_or_else(|| Error::with_message(Errno::ERANGE, "the offset is out of range"))?;
+    Ok((clockid, offset))
+}
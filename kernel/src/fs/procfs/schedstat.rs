@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! This module offers `/proc/schedstat` file support, which provides the
+//! scheduling statistics of each CPU in the format of version 15.
+//!
+//! Each line of a CPU contains the number of yields, a legacy field that is
+//! always zero, the number of schedules, the number of schedules that make the
+//! CPU idle, the number of wakeups, the number of local wakeups, the time that
+//! the threads run (ns), the time that the threads wait in the run queue (ns),
+//! and the number of time slices. The scheduling domains are not supported, so
+//! there are no domain lines.
+//!
+//! Reference: <https://docs.kernel.org/scheduler/sched-stats.html>
+
+use core::fmt::Write;
+
+use ostd::{cpu::all_cpus, timer::Jiffies};
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    prelude::*,
+    sched::cpu_sched_stat,
+};
+
+/// Represents the inode at `/proc/schedstat`.
+pub struct SchedStatFileOps;
+
+impl SchedStatFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self).parent(parent).build().unwrap()
+    }
+}
+
+impl FileOps for SchedStatFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let mut output = String::new();
+        writeln!(output, "version 15").unwrap();
+        writeln!(output, "timestamp {}", Jiffies::elapsed().as_u64()).unwrap();
+        for cpu in all_cpus() {
+            let stat = cpu_sched_stat(cpu);
+            writeln!(
+                output,
+                "cpu{} {} 0 {} {} {} {} {} {} {}",
+                cpu.as_usize(),
+                stat.nr_yields,
+                stat.nr_schedules,
+                stat.nr_idle_schedules,
+                stat.nr_wakeups,
+                stat.nr_local_wakeups,
+                stat.run_time_ns,
+                stat.run_delay_ns,
+                stat.nr_slices,
+            )
+            .unwrap();
+        }
+
+        Ok(output.into_bytes())
+    }
+}
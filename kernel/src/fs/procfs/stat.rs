@@ -0,0 +1,94 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! This module offers `/proc/stat` file support, which provides the time
+//! that the CPUs spend in each state since the system boots, and a few
+//! statistics of the processes.
+//!
+//! The time is measured in the units of `USER_HZ`. The states that are not
+//! accounted (e.g., `iowait` and `steal`) are always zero. The `intr`,
+//! `ctxt`, and `softirq` lines are not supported.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man5/proc_stat.5.html>
+
+use core::{fmt::Write, time::Duration};
+
+use aster_time::read_monotonic_time;
+use ostd::cpu::all_cpus;
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    prelude::*,
+    process::posix_thread,
+    sched::{self, cpu_times, CpuTimes},
+    time::{clocks::RealTimeClock, Clock},
+};
+
+/// The frequency of the clock ticks reported to the user space, which is
+/// the same as Linux.
+const USER_HZ: u128 = 100;
+
+/// Represents the inode at `/proc/stat`.
+pub struct StatFileOps;
+
+impl StatFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self).parent(parent).build().unwrap()
+    }
+}
+
+impl FileOps for StatFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let per_cpu_times = all_cpus()
+            .map(|cpu| (cpu, cpu_times(cpu)))
+            .collect::<Vec<_>>();
+
+        let mut total = CpuTimes::default();
+        for (_, times) in per_cpu_times.iter() {
+            total.user += times.user;
+            total.system += times.system;
+            total.idle += times.idle;
+            total.irq += times.irq;
+            total.softirq += times.softirq;
+        }
+
+        let mut output = String::new();
+        write_cpu_line(&mut output, "cpu", &total);
+        for (cpu, times) in per_cpu_times.iter() {
+            write_cpu_line(&mut output, &format!("cpu{}", cpu.as_usize()), times);
+        }
+
+        let boot_time = RealTimeClock::get()
+            .read_time()
+            .saturating_sub(read_monotonic_time());
+        let (nr_queued, nr_running) = sched::nr_queued_and_running();
+        writeln!(output, "btime {}", boot_time.as_secs()).unwrap();
+        // The threads are created by `fork` or `clone`, which are counted by Linux.
+        writeln!(output, "processes {}", posix_thread::last_tid()).unwrap();
+        writeln!(output, "procs_running {}", nr_queued + nr_running).unwrap();
+        // TODO: Count the threads that wait for the I/O.
+        writeln!(output, "procs_blocked 0").unwrap();
+
+        Ok(output.into_bytes())
+    }
+}
+
+/// Writes the times of the `user`, `nice`, `system`, `idle`, `iowait`,
+/// `irq`, `softirq`, `steal`, `guest`, and `guest_nice` states.
+fn write_cpu_line(output: &mut String, name: &str, times: &CpuTimes) {
+    let to_ticks = |duration: Duration| duration.as_nanos() * USER_HZ / 1_000_000_000;
+
+    writeln!(
+        output,
+        "{} {} 0 {} {} 0 {} {} 0 0 0",
+        name,
+        to_ticks(times.user),
+        to_ticks(times.system),
+        to_ticks(times.idle),
+        to_ticks(times.irq),
+        to_ticks(times.softirq),
+    )
+    .unwrap();
+}
@@ -3,7 +3,11 @@
 use crate::{
     fs::{
         procfs::{
-            sys::kernel::cap_last_cap::CapLastCapFileOps,
+            sys::kernel::{
+                cap_last_cap::CapLastCapFileOps,
+                panic_on_oops::PanicOnOopsFileOps,
+                uts_name::{DomainnameFileOps, HostnameFileOps},
+            },
             template::{DirOps, ProcDirBuilder},
             ProcDir,
         },
@@ -13,6 +17,8 @@ use crate::{
 };
 
 mod cap_last_cap;
+mod panic_on_oops;
+mod uts_name;
 
 /// Represents the inode at `/proc/sys/kernel`.
 pub struct KernelDirOps;
@@ -27,6 +33,9 @@ impl DirOps for KernelDirOps {
     fn lookup_child(&self, this_ptr: Weak<dyn Inode>, name: &str) -> Result<Arc<dyn Inode>> {
         let inode = match name {
             "cap_last_cap" => CapLastCapFileOps::new_inode(this_ptr.clone()),
+            "domainname" => DomainnameFileOps::new_inode(this_ptr.clone()),
+            "hostname" => HostnameFileOps::new_inode(this_ptr.clone()),
+            "panic_on_oops" => PanicOnOopsFileOps::new_inode(this_ptr.clone()),
             _ => return_errno!(Errno::ENOENT),
         };
         Ok(inode)
@@ -41,5 +50,13 @@ impl DirOps for KernelDirOps {
         cached_children.put_entry_if_not_found("cap_last_cap", || {
             CapLastCapFileOps::new_inode(this_ptr.clone())
         });
+        cached_children.put_entry_if_not_found("domainname", || {
+            DomainnameFileOps::new_inode(this_ptr.clone())
+        });
+        cached_children
+            .put_entry_if_not_found("hostname", || HostnameFileOps::new_inode(this_ptr.clone()));
+        cached_children.put_entry_if_not_found("panic_on_oops", || {
+            PanicOnOopsFileOps::new_inode(this_ptr.clone())
+        });
     }
 }
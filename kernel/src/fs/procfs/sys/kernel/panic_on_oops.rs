@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::format;
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
+    thread::oops,
+};
+
+/// Represents the inode at `/proc/sys/kernel/panic_on_oops`.
+pub struct PanicOnOopsFileOps;
+
+impl PanicOnOopsFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self)
+            .parent(parent)
+            .writable()
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for PanicOnOopsFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let output = format!("{}\n", oops::panic_on_oops() as i32);
+        Ok(output.into_bytes())
+    }
+
+    fn write_data(&self, data: &[u8]) -> Result<()> {
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        if !credentials.has_capability(CapSet::SYS_ADMIN) {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "changing the kernel parameters requires CAP_SYS_ADMIN"
+            );
+        }
+
+        // Like Linux, any non-zero value enables the panic.
+        let value = core::str::from_utf8(data)
+            .ok()
+            .and_then(|data| data.trim().parse::<i32>().ok())
+            .ok_or_else(|| {
+                Error::with_message(Errno::EINVAL, "the value is not a valid integer")
+            })?;
+        oops::set_panic_on_oops(value != 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::format;
+
+use crate::{
+    fs::{
+        procfs::{
+            template::{FileOps, ProcFileBuilder},
+            ProcPollEvent,
+        },
+        utils::Inode,
+    },
+    prelude::*,
+    process::namespace::uts_ns::UtsNamespace,
+};
+
+/// Represents the inode at `/proc/sys/kernel/hostname`.
+pub struct HostnameFileOps;
+
+impl HostnameFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self).parent(parent).build().unwrap()
+    }
+}
+
+impl FileOps for HostnameFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let uts_name = UtsNamespace::get_init_singleton().uts_name();
+        let output = format!("{}\n", String::from_utf8_lossy(uts_name.hostname()));
+        Ok(output.into_bytes())
+    }
+
+    fn poll_event(&self) -> Option<&ProcPollEvent> {
+        Some(UtsNamespace::get_init_singleton().hostname_event())
+    }
+}
+
+/// Represents the inode at `/proc/sys/kernel/domainname`.
+pub struct DomainnameFileOps;
+
+impl DomainnameFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self).parent(parent).build().unwrap()
+    }
+}
+
+impl FileOps for DomainnameFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let uts_name = UtsNamespace::get_init_singleton().uts_name();
+        let output = format!("{}\n", String::from_utf8_lossy(uts_name.domainname()));
+        Ok(output.into_bytes())
+    }
+
+    fn poll_event(&self) -> Option<&ProcPollEvent> {
+        Some(UtsNamespace::get_init_singleton().domainname_event())
+    }
+}
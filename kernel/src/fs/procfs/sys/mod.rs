@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use self::kernel::KernelDirOps;
+use self::{kernel::KernelDirOps, net::NetDirOps, vm::VmDirOps};
 use crate::{
     fs::{
         procfs::template::{DirOps, ProcDir, ProcDirBuilder},
@@ -10,6 +10,8 @@ use crate::{
 };
 
 mod kernel;
+mod net;
+mod vm;
 
 /// Represents the inode at `/proc/sys`.
 pub struct SysDirOps;
@@ -24,6 +26,8 @@ impl DirOps for SysDirOps {
     fn lookup_child(&self, this_ptr: Weak<dyn Inode>, name: &str) -> Result<Arc<dyn Inode>> {
         let inode = match name {
             "kernel" => KernelDirOps::new_inode(this_ptr.clone()),
+            "net" => NetDirOps::new_inode(this_ptr.clone()),
+            "vm" => VmDirOps::new_inode(this_ptr.clone()),
             _ => return_errno!(Errno::ENOENT),
         };
         Ok(inode)
@@ -36,6 +40,8 @@ impl DirOps for SysDirOps {
         };
         let mut cached_children = this.cached_children().write();
         cached_children
-            .put_entry_if_not_found("kernel", || KernelDirOps::new_inode(this_ptr.clone()))
+            .put_entry_if_not_found("kernel", || KernelDirOps::new_inode(this_ptr.clone()));
+        cached_children.put_entry_if_not_found("net", || NetDirOps::new_inode(this_ptr.clone()));
+        cached_children.put_entry_if_not_found("vm", || VmDirOps::new_inode(this_ptr.clone()));
     }
 }
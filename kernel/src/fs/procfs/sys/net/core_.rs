@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::format;
+
+use super::parse_net_param;
+use crate::{
+    fs::{
+        procfs::template::{DirOps, FileOps, ProcDir, ProcDirBuilder, ProcFileBuilder},
+        utils::{DirEntryVecExt, Inode},
+    },
+    net::sysctl,
+    prelude::*,
+};
+
+/// Represents the inode at `/proc/sys/net/core`.
+pub struct CoreDirOps;
+
+impl CoreDirOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcDirBuilder::new(Self).parent(parent).build().unwrap()
+    }
+}
+
+impl DirOps for CoreDirOps {
+    fn lookup_child(&self, this_ptr: Weak<dyn Inode>, name: &str) -> Result<Arc<dyn Inode>> {
+        let inode = match name {
+            "somaxconn" => SomaxconnFileOps::new_inode(this_ptr.clone()),
+            _ => return_errno!(Errno::ENOENT),
+        };
+        Ok(inode)
+    }
+
+    fn populate_children(&self, this_ptr: Weak<dyn Inode>) {
+        let this = {
+            let this = this_ptr.upgrade().unwrap();
+            this.downcast_ref::<ProcDir<CoreDirOps>>().unwrap().this()
+        };
+        let mut cached_children = this.cached_children().write();
+        cached_children.put_entry_if_not_found("somaxconn", || {
+            SomaxconnFileOps::new_inode(this_ptr.clone())
+        });
+    }
+}
+
+/// Represents the inode at `/proc/sys/net/core/somaxconn`.
+pub struct SomaxconnFileOps;
+
+impl SomaxconnFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self)
+            .parent(parent)
+            .writable()
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for SomaxconnFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let output = format!("{}\n", sysctl::somaxconn());
+        Ok(output.into_bytes())
+    }
+
+    fn write_data(&self, data: &[u8]) -> Result<()> {
+        sysctl::set_somaxconn(parse_net_param(data)?)
+    }
+}
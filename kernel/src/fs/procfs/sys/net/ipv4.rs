@@ -0,0 +1,126 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::format;
+
+use super::parse_net_param;
+use crate::{
+    fs::{
+        procfs::template::{DirOps, FileOps, ProcDir, ProcDirBuilder, ProcFileBuilder},
+        utils::{DirEntryVecExt, Inode},
+    },
+    net::sysctl,
+    prelude::*,
+};
+
+/// Represents the inode at `/proc/sys/net/ipv4`.
+pub struct Ipv4DirOps;
+
+impl Ipv4DirOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcDirBuilder::new(Self).parent(parent).build().unwrap()
+    }
+}
+
+impl DirOps for Ipv4DirOps {
+    fn lookup_child(&self, this_ptr: Weak<dyn Inode>, name: &str) -> Result<Arc<dyn Inode>> {
+        let inode = match name {
+            "ip_unprivileged_port_start" => {
+                IpUnprivilegedPortStartFileOps::new_inode(this_ptr.clone())
+            }
+            "tcp_fastopen" => TcpFastOpenFileOps::new_inode(this_ptr.clone()),
+            "tcp_max_tw_buckets" => TcpMaxTwBucketsFileOps::new_inode(this_ptr.clone()),
+            _ => return_errno!(Errno::ENOENT),
+        };
+        Ok(inode)
+    }
+
+    fn populate_children(&self, this_ptr: Weak<dyn Inode>) {
+        let this = {
+            let this = this_ptr.upgrade().unwrap();
+            this.downcast_ref::<ProcDir<Ipv4DirOps>>().unwrap().this()
+        };
+        let mut cached_children = this.cached_children().write();
+        cached_children.put_entry_if_not_found("ip_unprivileged_port_start", || {
+            IpUnprivilegedPortStartFileOps::new_inode(this_ptr.clone())
+        });
+        cached_children.put_entry_if_not_found("tcp_fastopen", || {
+            TcpFastOpenFileOps::new_inode(this_ptr.clone())
+        });
+        cached_children.put_entry_if_not_found("tcp_max_tw_buckets", || {
+            TcpMaxTwBucketsFileOps::new_inode(this_ptr.clone())
+        });
+    }
+}
+
+/// Represents the inode at `/proc/sys/net/ipv4/ip_unprivileged_port_start`.
+pub struct IpUnprivilegedPortStartFileOps;
+
+impl IpUnprivilegedPortStartFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self)
+            .parent(parent)
+            .writable()
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for IpUnprivilegedPortStartFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let output = format!("{}\n", sysctl::ip_unprivileged_port_start());
+        Ok(output.into_bytes())
+    }
+
+    fn write_data(&self, data: &[u8]) -> Result<()> {
+        sysctl::set_ip_unprivileged_port_start(parse_net_param(data)?)
+    }
+}
+
+/// Represents the inode at `/proc/sys/net/ipv4/tcp_fastopen`.
+pub struct TcpFastOpenFileOps;
+
+impl TcpFastOpenFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self)
+            .parent(parent)
+            .writable()
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for TcpFastOpenFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let output = format!("{}\n", sysctl::tcp_fastopen());
+        Ok(output.into_bytes())
+    }
+
+    fn write_data(&self, data: &[u8]) -> Result<()> {
+        sysctl::set_tcp_fastopen(parse_net_param(data)?);
+        Ok(())
+    }
+}
+
+/// Represents the inode at `/proc/sys/net/ipv4/tcp_max_tw_buckets`.
+pub struct TcpMaxTwBucketsFileOps;
+
+impl TcpMaxTwBucketsFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self)
+            .parent(parent)
+            .writable()
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for TcpMaxTwBucketsFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let output = format!("{}\n", sysctl::tcp_max_tw_buckets());
+        Ok(output.into_bytes())
+    }
+
+    fn write_data(&self, data: &[u8]) -> Result<()> {
+        sysctl::set_tcp_max_tw_buckets(parse_net_param(data)?)
+    }
+}
@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use self::{core_::CoreDirOps, ipv4::Ipv4DirOps};
+use crate::{
+    fs::{
+        procfs::template::{DirOps, ProcDir, ProcDirBuilder},
+        utils::{DirEntryVecExt, Inode},
+    },
+    prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
+};
+
+mod core_;
+mod ipv4;
+
+/// Represents the inode at `/proc/sys/net`.
+pub struct NetDirOps;
+
+impl NetDirOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcDirBuilder::new(Self).parent(parent).build().unwrap()
+    }
+}
+
+impl DirOps for NetDirOps {
+    fn lookup_child(&self, this_ptr: Weak<dyn Inode>, name: &str) -> Result<Arc<dyn Inode>> {
+        let inode = match name {
+            "core" => CoreDirOps::new_inode(this_ptr.clone()),
+            "ipv4" => Ipv4DirOps::new_inode(this_ptr.clone()),
+            _ => return_errno!(Errno::ENOENT),
+        };
+        Ok(inode)
+    }
+
+    fn populate_children(&self, this_ptr: Weak<dyn Inode>) {
+        let this = {
+            let this = this_ptr.upgrade().unwrap();
+            this.downcast_ref::<ProcDir<NetDirOps>>().unwrap().this()
+        };
+        let mut cached_children = this.cached_children().write();
+        cached_children.put_entry_if_not_found("core", || CoreDirOps::new_inode(this_ptr.clone()));
+        cached_children.put_entry_if_not_found("ipv4", || Ipv4DirOps::new_inode(this_ptr.clone()));
+    }
+}
+
+/// Parses the integer written to a network parameter file.
+///
+/// Like Linux, this requires `CAP_NET_ADMIN` to change the network parameters.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/sysctl_net.c#L44>
+fn parse_net_param(data: &[u8]) -> Result<i32> {
+    let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+    if !credentials.has_capability(CapSet::NET_ADMIN) {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "changing the network parameters requires CAP_NET_ADMIN"
+        );
+    }
+
+    core::str::from_utf8(data)
+        .ok()
+        .and_then(|data| data.trim().parse::<i32>().ok())
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the value is not a valid integer"))
+}
@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::format;
+
+use super::overcommit::parse_value;
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    prelude::*,
+    vm::hugetlb,
+};
+
+/// Represents the inode at `/proc/sys/vm/nr_hugepages`.
+pub struct NrHugePagesFileOps;
+
+impl NrHugePagesFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self)
+            .parent(parent)
+            .writable()
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for NrHugePagesFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let output = format!("{}\n", hugetlb::default_pool().nr_pages());
+        Ok(output.into_bytes())
+    }
+
+    fn write_data(&self, data: &[u8]) -> Result<()> {
+        let nr_pages = parse_value::<usize>(data)?;
+        hugetlb::default_pool().set_nr_pages(nr_pages);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    fs::{
+        procfs::{
+            sys::vm::{
+                hugetlb::NrHugePagesFileOps,
+                overcommit::{
+                    OvercommitKbytesFileOps, OvercommitMemoryFileOps, OvercommitRatioFileOps,
+                },
+            },
+            template::{DirOps, ProcDirBuilder},
+            ProcDir,
+        },
+        utils::{DirEntryVecExt, Inode},
+    },
+    prelude::*,
+};
+
+mod hugetlb;
+mod overcommit;
+
+/// Represents the inode at `/proc/sys/vm`.
+pub struct VmDirOps;
+
+impl VmDirOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcDirBuilder::new(Self).parent(parent).build().unwrap()
+    }
+}
+
+impl DirOps for VmDirOps {
+    fn lookup_child(&self, this_ptr: Weak<dyn Inode>, name: &str) -> Result<Arc<dyn Inode>> {
+        let inode = match name {
+            "nr_hugepages" => NrHugePagesFileOps::new_inode(this_ptr.clone()),
+            "overcommit_kbytes" => OvercommitKbytesFileOps::new_inode(this_ptr.clone()),
+            "overcommit_memory" => OvercommitMemoryFileOps::new_inode(this_ptr.clone()),
+            "overcommit_ratio" => OvercommitRatioFileOps::new_inode(this_ptr.clone()),
+            _ => return_errno!(Errno::ENOENT),
+        };
+        Ok(inode)
+    }
+
+    fn populate_children(&self, this_ptr: Weak<dyn Inode>) {
+        let this = {
+            let this = this_ptr.upgrade().unwrap();
+            this.downcast_ref::<ProcDir<VmDirOps>>().unwrap().this()
+        };
+        let mut cached_children = this.cached_children().write();
+        cached_children.put_entry_if_not_found("nr_hugepages", || {
+            NrHugePagesFileOps::new_inode(this_ptr.clone())
+        });
+        cached_children.put_entry_if_not_found("overcommit_kbytes", || {
+            OvercommitKbytesFileOps::new_inode(this_ptr.clone())
+        });
+        cached_children.put_entry_if_not_found("overcommit_memory", || {
+            OvercommitMemoryFileOps::new_inode(this_ptr.clone())
+        });
+        cached_children.put_entry_if_not_found("overcommit_ratio", || {
+            OvercommitRatioFileOps::new_inode(this_ptr.clone())
+        });
+    }
+}
@@ -0,0 +1,112 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::format;
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
+    vm::overcommit::{self, OvercommitMode},
+};
+
+/// Represents the inode at `/proc/sys/vm/overcommit_memory`.
+pub struct OvercommitMemoryFileOps;
+
+impl OvercommitMemoryFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self)
+            .parent(parent)
+            .writable()
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for OvercommitMemoryFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let output = format!("{}\n", overcommit::overcommit_memory() as u32);
+        Ok(output.into_bytes())
+    }
+
+    fn write_data(&self, data: &[u8]) -> Result<()> {
+        let value = parse_value::<u32>(data)?;
+        let mode = OvercommitMode::try_from(value)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the overcommit mode is invalid"))?;
+        overcommit::set_overcommit_memory(mode);
+
+        Ok(())
+    }
+}
+
+/// Represents the inode at `/proc/sys/vm/overcommit_ratio`.
+pub struct OvercommitRatioFileOps;
+
+impl OvercommitRatioFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self)
+            .parent(parent)
+            .writable()
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for OvercommitRatioFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let output = format!("{}\n", overcommit::overcommit_ratio());
+        Ok(output.into_bytes())
+    }
+
+    fn write_data(&self, data: &[u8]) -> Result<()> {
+        let ratio = parse_value::<usize>(data)?;
+        overcommit::set_overcommit_ratio(ratio);
+
+        Ok(())
+    }
+}
+
+/// Represents the inode at `/proc/sys/vm/overcommit_kbytes`.
+pub struct OvercommitKbytesFileOps;
+
+impl OvercommitKbytesFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self)
+            .parent(parent)
+            .writable()
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for OvercommitKbytesFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let output = format!("{}\n", overcommit::overcommit_kbytes());
+        Ok(output.into_bytes())
+    }
+
+    fn write_data(&self, data: &[u8]) -> Result<()> {
+        let kbytes = parse_value::<usize>(data)?;
+        overcommit::set_overcommit_kbytes(kbytes);
+
+        Ok(())
+    }
+}
+
+/// Checks the permission and parses the value written to a file.
+pub(super) fn parse_value<T: core::str::FromStr>(data: &[u8]) -> Result<T> {
+    let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+    if !credentials.has_capability(CapSet::SYS_ADMIN) {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "changing the VM parameters requires CAP_SYS_ADMIN"
+        );
+    }
+
+    core::str::from_utf8(data)
+        .ok()
+        .and_then(|data| data.trim().parse::<T>().ok())
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the value is not a valid integer"))
+}
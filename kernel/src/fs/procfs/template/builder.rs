@@ -4,7 +4,8 @@
 
 use super::{
     dir::{DirOps, ProcDir},
-    file::{FileOps, ProcFile},
+    file::ProcFile,
+    seq::SeqOps,
     sym::{ProcSym, SymOps},
 };
 use crate::{
@@ -59,22 +60,32 @@ impl<O: DirOps> ProcDirBuilder<O> {
     }
 }
 
-pub struct ProcFileBuilder<O: FileOps> {
+pub struct ProcFileBuilder<O: SeqOps> {
     // Mandatory field
     file: O,
     // Optional fields
     optional_builder: Option<OptionalBuilder>,
+    is_writable: bool,
 }
 
-impl<O: FileOps> ProcFileBuilder<O> {
+impl<O: SeqOps> ProcFileBuilder<O> {
     pub fn new(file: O) -> Self {
         let optional_builder: OptionalBuilder = Default::default();
         Self {
             file,
             optional_builder: Some(optional_builder),
+            is_writable: false,
         }
     }
 
+    /// Makes the file writable by its owner.
+    ///
+    /// The file should implement [`SeqOps::write_data`].
+    pub fn writable(mut self) -> Self {
+        self.is_writable = true;
+        self
+    }
+
     pub fn parent(self, parent: Weak<dyn Inode>) -> Self {
         self.optional_builder(|ob| ob.parent(parent))
     }
@@ -85,7 +96,7 @@ impl<O: FileOps> ProcFileBuilder<O> {
 
     pub fn build(mut self) -> Result<Arc<ProcFile<O>>> {
         let (fs, _, _, is_volatile) = self.optional_builder.take().unwrap().build()?;
-        Ok(ProcFile::new(self.file, fs, is_volatile))
+        Ok(ProcFile::new(self.file, fs, is_volatile, self.is_writable))
     }
 
     fn optional_builder<F>(mut self, f: F) -> Self
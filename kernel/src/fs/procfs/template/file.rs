@@ -4,39 +4,51 @@ use core::time::Duration;
 
 use inherit_methods_macro::inherit_methods;
 
-use super::{Common, ProcFS};
+use super::{
+    seq::{write_to, SeqFile, SeqState},
+    Common, ProcFS, ProcPollEvent, SeqOps,
+};
 use crate::{
-    fs::utils::{FileSystem, Inode, InodeMode, InodeType, IoctlCmd, Metadata},
+    fs::{
+        inode_handle::FileIo,
+        utils::{FileSystem, Inode, InodeMode, InodeType, IoctlCmd, Metadata},
+    },
     prelude::*,
     process::{Gid, Uid},
 };
 
-pub struct ProcFile<F: FileOps> {
-    inner: F,
+pub struct ProcFile<F: SeqOps> {
+    inner: Arc<F>,
     common: Common,
 }
 
-impl<F: FileOps> ProcFile<F> {
-    pub fn new(file: F, fs: Weak<dyn FileSystem>, is_volatile: bool) -> Arc<Self> {
+impl<F: SeqOps> ProcFile<F> {
+    pub fn new(
+        file: F,
+        fs: Weak<dyn FileSystem>,
+        is_volatile: bool,
+        is_writable: bool,
+    ) -> Arc<Self> {
         let common = {
             let arc_fs = fs.upgrade().unwrap();
             let procfs = arc_fs.downcast_ref::<ProcFS>().unwrap();
+            let mode = if is_writable { 0o644 } else { 0o444 };
             let metadata = Metadata::new_file(
                 procfs.alloc_id(),
-                InodeMode::from_bits_truncate(0o444),
+                InodeMode::from_bits_truncate(mode),
                 super::BLOCK_SIZE,
             );
             Common::new(metadata, fs, is_volatile)
         };
         Arc::new(Self {
-            inner: file,
+            inner: Arc::new(file),
             common,
         })
     }
 }
 
 #[inherit_methods(from = "self.common")]
-impl<F: FileOps + 'static> Inode for ProcFile<F> {
+impl<F: SeqOps + 'static> Inode for ProcFile<F> {
     fn size(&self) -> usize;
     fn metadata(&self) -> Metadata;
     fn ino(&self) -> u64;
@@ -63,24 +75,19 @@ impl<F: FileOps + 'static> Inode for ProcFile<F> {
     }
 
     fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
-        let data = self.inner.data()?;
-        let start = data.len().min(offset);
-        let end = data.len().min(offset + writer.avail());
-        let len = end - start;
-        writer.write_fallible(&mut (&data[start..end]).into())?;
-        Ok(len)
+        SeqState::new().read_at(self.inner.as_ref(), offset, writer)
     }
 
     fn read_direct_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
         self.read_at(offset, writer)
     }
 
-    fn write_at(&self, _offset: usize, _reader: &mut VmReader) -> Result<usize> {
-        Err(Error::new(Errno::EPERM))
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        write_to(self.inner.as_ref(), reader)
     }
 
-    fn write_direct_at(&self, _offset: usize, _reader: &mut VmReader) -> Result<usize> {
-        Err(Error::new(Errno::EPERM))
+    fn write_direct_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        self.write_at(offset, reader)
     }
 
     fn read_link(&self) -> Result<String> {
@@ -95,11 +102,32 @@ impl<F: FileOps + 'static> Inode for ProcFile<F> {
         Err(Error::new(Errno::EPERM))
     }
 
+    fn open(&self) -> Result<Option<Arc<dyn FileIo>>> {
+        Ok(Some(Arc::new(SeqFile::new(self.inner.clone()))))
+    }
+
     fn is_dentry_cacheable(&self) -> bool {
         !self.common.is_volatile()
     }
 }
 
+/// The operations of a procfs file whose content is generated in one go.
+///
+/// For a large file, consider implementing [`SeqOps`] instead.
 pub trait FileOps: Sync + Send {
     fn data(&self) -> Result<Vec<u8>>;
+
+    /// Writes `data` to the file.
+    ///
+    /// See [`SeqOps::write_data`] for details.
+    fn write_data(&self, _data: &[u8]) -> Result<()> {
+        return_errno_with_message!(Errno::EPERM, "procfs files cannot be written");
+    }
+
+    /// Returns the change notification of the file.
+    ///
+    /// See [`SeqOps::poll_event`] for details.
+    fn poll_event(&self) -> Option<&ProcPollEvent> {
+        None
+    }
 }
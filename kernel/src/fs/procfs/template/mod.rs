@@ -6,6 +6,8 @@ pub use self::{
     builder::{ProcDirBuilder, ProcFileBuilder, ProcSymBuilder},
     dir::{DirOps, ProcDir},
     file::FileOps,
+    poll::ProcPollEvent,
+    seq::SeqOps,
     sym::SymOps,
 };
 use super::{ProcFS, BLOCK_SIZE};
@@ -18,6 +20,8 @@ use crate::{
 mod builder;
 mod dir;
 mod file;
+mod poll;
+mod seq;
 mod sym;
 
 struct Common {
@@ -0,0 +1,69 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{
+    events::IoEvents,
+    prelude::*,
+    process::signal::{PollHandle, Pollee},
+};
+
+/// A change notification of pollable procfs files.
+///
+/// The opened files are reported to have [`IoEvents::PRI`] and [`IoEvents::ERR`] once after
+/// each change, like the pollable sysctl files of Linux. The changes that happen before the
+/// files are opened are not reported.
+pub struct ProcPollEvent {
+    count: AtomicUsize,
+    pollee: Pollee,
+}
+
+impl ProcPollEvent {
+    /// The events that are reported after a change.
+    const EVENTS: IoEvents = IoEvents::PRI.union(IoEvents::ERR);
+
+    pub fn new() -> Self {
+        Self {
+            count: AtomicUsize::new(0),
+            pollee: Pollee::new(),
+        }
+    }
+
+    /// Notifies the pollers that the content of the files has changed.
+    pub fn notify(&self) {
+        self.count.fetch_add(1, Ordering::Release);
+        self.pollee.notify(Self::EVENTS);
+    }
+
+    /// Returns the number of changes, which is the initial value of `seen` in [`Self::poll`].
+    pub(super) fn count(&self) -> usize {
+        self.count.load(Ordering::Acquire)
+    }
+
+    /// Polls the events, where `seen` is the number of changes that have been reported.
+    pub(super) fn poll(
+        &self,
+        seen: &AtomicUsize,
+        mask: IoEvents,
+        poller: Option<&mut PollHandle>,
+    ) -> IoEvents {
+        // Register the poller before checking the count, so that no changes are missed.
+        if let Some(poller) = poller {
+            self.pollee
+                .register_poller(poller, mask | IoEvents::ALWAYS_POLL);
+        }
+
+        let count = self.count();
+        if seen.swap(count, Ordering::Relaxed) != count {
+            Self::EVENTS
+        } else {
+            IoEvents::empty()
+        }
+    }
+}
+
+impl Default for ProcPollEvent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,234 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::AtomicUsize;
+
+use super::{FileOps, ProcPollEvent};
+use crate::{
+    events::IoEvents,
+    fs::inode_handle::FileIo,
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+};
+
+/// The operations of a procfs file whose content is generated record by record.
+///
+/// The content is generated lazily as the file is read, and the generated records are kept
+/// in the opened file, like the `seq_file` of Linux. So a large file does not have to be
+/// generated in whole, and small sequential reads see each record at most once.
+pub trait SeqOps: Sync + Send {
+    /// Shows the records from the `pos`-th one into `buf`.
+    ///
+    /// This method returns the position after the last shown record. It should show at least
+    /// one record if there is any at `pos`, and return `pos` itself at the end of the file.
+    fn show(&self, pos: usize, buf: &mut Vec<u8>) -> Result<usize>;
+
+    /// Writes `data` to the file.
+    ///
+    /// Most procfs files are read-only, so the default implementation fails with
+    /// [`Errno::EPERM`].
+    fn write_data(&self, _data: &[u8]) -> Result<()> {
+        return_errno_with_message!(Errno::EPERM, "procfs files cannot be written");
+    }
+
+    /// Returns the change notification of the file.
+    ///
+    /// Most procfs files cannot be polled for changes, so the default implementation returns
+    /// `None`. Otherwise, the opened files are reported to have [`IoEvents::PRI`] and
+    /// [`IoEvents::ERR`] once after each change.
+    fn poll_event(&self) -> Option<&ProcPollEvent> {
+        None
+    }
+}
+
+/// A file whose content is generated in one go is a file of only one record.
+impl<F: FileOps> SeqOps for F {
+    fn show(&self, pos: usize, buf: &mut Vec<u8>) -> Result<usize> {
+        if pos > 0 {
+            return Ok(pos);
+        }
+
+        buf.append(&mut self.data()?);
+        Ok(1)
+    }
+
+    fn write_data(&self, data: &[u8]) -> Result<()> {
+        FileOps::write_data(self, data)
+    }
+
+    fn poll_event(&self) -> Option<&ProcPollEvent> {
+        FileOps::poll_event(self)
+    }
+}
+
+/// Writes the data in `reader` to a procfs file.
+///
+/// The whole data is passed to the file in one go, so it should not exceed one page.
+pub(super) fn write_to(ops: &dyn SeqOps, reader: &mut VmReader) -> Result<usize> {
+    if reader.remain() > PAGE_SIZE {
+        return_errno_with_message!(Errno::EINVAL, "the data is too long");
+    }
+
+    let data = reader.collect()?;
+    ops.write_data(&data)?;
+    Ok(data.len())
+}
+
+/// The generated content of an opened procfs file.
+pub(super) struct SeqState {
+    /// The content that is generated but not read yet.
+    buf: Vec<u8>,
+    /// The file offset of the first byte in `buf`.
+    buf_offset: usize,
+    /// The position of the next record to show.
+    next_pos: usize,
+    is_eof: bool,
+}
+
+impl SeqState {
+    pub(super) fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            buf_offset: 0,
+            next_pos: 0,
+            is_eof: false,
+        }
+    }
+
+    pub(super) fn read_at(
+        &mut self,
+        ops: &dyn SeqOps,
+        offset: usize,
+        writer: &mut VmWriter,
+    ) -> Result<usize> {
+        // The records before the offset are dropped, so they are generated again from the start.
+        if offset < self.buf_offset {
+            *self = Self::new();
+        }
+
+        let mut read_len = 0;
+        while writer.has_avail() {
+            let file_offset = offset + read_len;
+            let buf_end = self.buf_offset + self.buf.len();
+            if file_offset < buf_end {
+                let start = file_offset - self.buf_offset;
+                read_len += writer.write_fallible(&mut (&self.buf[start..]).into())?;
+                continue;
+            }
+            if self.is_eof {
+                break;
+            }
+
+            // All the generated content has been read or skipped.
+            self.buf_offset = buf_end;
+            self.buf.clear();
+            let next_pos = ops.show(self.next_pos, &mut self.buf)?;
+            self.is_eof = next_pos == self.next_pos;
+            self.next_pos = next_pos;
+        }
+
+        Ok(read_len)
+    }
+}
+
+/// An opened procfs file that keeps the generated content between reads.
+pub(super) struct SeqFile<S: SeqOps> {
+    ops: Arc<S>,
+    state: Mutex<SeqState>,
+    /// The number of changes that have been reported, if the file is pollable for changes.
+    seen_events: AtomicUsize,
+}
+
+impl<S: SeqOps> SeqFile<S> {
+    pub(super) fn new(ops: Arc<S>) -> Self {
+        let seen_events = ops.poll_event().map_or(0, ProcPollEvent::count);
+
+        Self {
+            ops,
+            state: Mutex::new(SeqState::new()),
+            seen_events: AtomicUsize::new(seen_events),
+        }
+    }
+}
+
+impl<S: SeqOps> Pollable for SeqFile<S> {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        let mut events = IoEvents::IN | IoEvents::OUT;
+        if let Some(poll_event) = self.ops.poll_event() {
+            events |= poll_event.poll(&self.seen_events, mask, poller);
+        }
+        events & (mask | IoEvents::ALWAYS_POLL)
+    }
+}
+
+impl<S: SeqOps + 'static> FileIo for SeqFile<S> {
+    fn read(&self, writer: &mut VmWriter) -> Result<usize> {
+        self.read_at(0, writer)
+    }
+
+    fn write(&self, reader: &mut VmReader) -> Result<usize> {
+        write_to(self.ops.as_ref(), reader)
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        true
+    }
+
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        self.state.lock().read_at(self.ops.as_ref(), offset, writer)
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    /// A file of the lines `0\n`, `1\n`, ..., each of which is a record.
+    struct Lines(usize);
+
+    impl SeqOps for Lines {
+        fn show(&self, pos: usize, buf: &mut Vec<u8>) -> Result<usize> {
+            if pos >= self.0 {
+                return Ok(pos);
+            }
+            buf.extend_from_slice(format!("{}\n", pos).as_bytes());
+            Ok(pos + 1)
+        }
+    }
+
+    fn read(state: &mut SeqState, ops: &dyn SeqOps, offset: usize, len: usize) -> Vec<u8> {
+        let mut buf = vec![0u8; len];
+        let read_len = state
+            .read_at(
+                ops,
+                offset,
+                &mut VmWriter::from(buf.as_mut_slice()).to_fallible(),
+            )
+            .unwrap();
+        buf.truncate(read_len);
+        buf
+    }
+
+    #[ktest]
+    fn read_records() {
+        let ops = Lines(12);
+        let mut state = SeqState::new();
+
+        // Small sequential reads.
+        let mut content = Vec::new();
+        loop {
+            let buf = read(&mut state, &ops, content.len(), 3);
+            if buf.is_empty() {
+                break;
+            }
+            content.extend(buf);
+        }
+        assert_eq!(content, b"0\n1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n");
+
+        // Seeking backward and forward.
+        assert_eq!(read(&mut state, &ops, 2, 4), b"1\n2\n");
+        assert_eq!(read(&mut state, &ops, 21, 100), b"0\n11\n");
+        assert_eq!(read(&mut state, &ops, 100, 1), b"");
+    }
+}
@@ -0,0 +1,46 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! This module offers `/proc/uptime` file support, which provides the time
+//! since the system boots and the sum of the time that each CPU is idle.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man5/proc_uptime.5.html>
+
+use core::time::Duration;
+
+use aster_time::read_monotonic_time;
+use ostd::cpu::all_cpus;
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    prelude::*,
+    sched::cpu_times,
+};
+
+/// Represents the inode at `/proc/uptime`.
+pub struct UptimeFileOps;
+
+impl UptimeFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self).parent(parent).build().unwrap()
+    }
+}
+
+impl FileOps for UptimeFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let uptime = read_monotonic_time();
+        let idle = all_cpus().map(|cpu| cpu_times(cpu).idle).sum::<Duration>();
+
+        let output = format!(
+            "{}.{:02} {}.{:02}\n",
+            uptime.as_secs(),
+            uptime.subsec_millis() / 10,
+            idle.as_secs(),
+            idle.subsec_millis() / 10,
+        );
+
+        Ok(output.into_bytes())
+    }
+}
@@ -0,0 +1,46 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! This module offers `/proc/vmstat` file support, which tells the user space
+//! about the VM statistics in the entire system.
+//!
+//! Each line consists of a name and a value. The counters of the pages come
+//! first, followed by the event counters since boot. Only a subset of the
+//! Linux fields is supported.
+//!
+//! Reference: <https://github.com/torvalds/linux/blob/master/mm/vmstat.c>
+
+use core::fmt::Write;
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::{nr_dirty_pages, Inode},
+    },
+    prelude::*,
+    vm::stat::{self, VmEvent},
+};
+
+/// Represents the inode at `/proc/vmstat`.
+pub struct VmStatFileOps;
+
+impl VmStatFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self).parent(parent).build().unwrap()
+    }
+}
+
+impl FileOps for VmStatFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let nr_free_pages = osdk_frame_allocator::load_total_free_size() / PAGE_SIZE;
+        let nr_page_table_pages = ostd::mm::nr_page_table_pages();
+
+        let mut output = String::new();
+        writeln!(output, "nr_free_pages {}", nr_free_pages).unwrap();
+        writeln!(output, "nr_dirty {}", nr_dirty_pages()).unwrap();
+        writeln!(output, "nr_page_table_pages {}", nr_page_table_pages).unwrap();
+        for event in VmEvent::ALL {
+            writeln!(output, "{} {}", event.name(), stat::vm_event(event)).unwrap();
+        }
+        Ok(output.into_bytes())
+    }
+}
@@ -15,13 +15,16 @@ use ostd::{
     sync::{PreemptDisabled, RwLockWriteGuard},
 };
 
-use super::{xattr::RamXattr, *};
+use super::{
+    huge_file::{HugeFile, HugeFileIo},
+    xattr::RamXattr,
+    *,
+};
 use crate::{
     events::IoEvents,
     fs::{
         device::Device,
-        file_handle::FileLike,
-        named_pipe::NamedPipe,
+        inode_handle::FileIo,
         path::{is_dot, is_dot_or_dotdot, is_dotdot},
         utils::{
             CStr256, CachePage, DirentVisitor, Extension, FallocMode, FileSystem, FsFlags, Inode,
@@ -32,7 +35,7 @@ use crate::{
     prelude::*,
     process::{signal::PollHandle, Gid, Uid},
     time::clocks::RealTimeCoarseClock,
-    vm::vmo::Vmo,
+    vm::{hugetlb::HugePageSubpool, vmo::Vmo},
 };
 
 /// A volatile file system whose data and metadata exists only in memory.
@@ -43,12 +46,27 @@ pub struct RamFS {
     root: Arc<RamInode>,
     /// An inode allocator
     inode_allocator: AtomicU64,
+    /// The huge pages of the regular files if the file system is a hugetlbfs
+    huge_subpool: Option<Arc<HugePageSubpool>>,
 }
 
 impl RamFS {
     pub fn new() -> Arc<Self> {
+        Self::new_with(SuperBlock::new(RAMFS_MAGIC, BLOCK_SIZE, NAME_MAX), None)
+    }
+
+    /// Creates a hugetlbfs, whose regular files are backed by the huge pages of the subpool.
+    pub fn new_hugetlbfs(subpool: Arc<HugePageSubpool>) -> Arc<Self> {
+        let page_size = subpool.pool().page_size();
+        Self::new_with(
+            SuperBlock::new(HUGETLBFS_MAGIC, page_size, NAME_MAX),
+            Some(subpool),
+        )
+    }
+
+    fn new_with(sb: SuperBlock, huge_subpool: Option<Arc<HugePageSubpool>>) -> Arc<Self> {
         Arc::new_cyclic(|weak_fs| Self {
-            sb: SuperBlock::new(RAMFS_MAGIC, BLOCK_SIZE, NAME_MAX),
+            sb,
             root: Arc::new_cyclic(|weak_root| RamInode {
                 inner: Inner::new_dir(weak_root.clone(), weak_root.clone()),
                 metadata: SpinLock::new(InodeMeta::new_dir(
@@ -64,6 +82,7 @@ impl RamFS {
                 xattr: RamXattr::new(),
             }),
             inode_allocator: AtomicU64::new(ROOT_INO + 1),
+            huge_subpool,
         })
     }
 
@@ -115,10 +134,11 @@ struct RamInode {
 enum Inner {
     Dir(RwLock<DirEntry>),
     File(PageCache),
+    HugeFile(Arc<HugeFile>),
     SymLink(SpinLock<String>),
     Device(Arc<dyn Device>),
     Socket,
-    NamedPipe(NamedPipe),
+    NamedPipe,
 }
 
 impl Inner {
@@ -130,6 +150,10 @@ impl Inner {
         Self::File(PageCache::new(this).unwrap())
     }
 
+    pub fn new_huge_file(subpool: Arc<HugePageSubpool>) -> Self {
+        Self::HugeFile(Arc::new(HugeFile::new(subpool)))
+    }
+
     pub fn new_symlink() -> Self {
         Self::SymLink(SpinLock::new(String::from("")))
     }
@@ -143,7 +167,7 @@ impl Inner {
     }
 
     pub fn new_named_pipe() -> Self {
-        Self::NamedPipe(NamedPipe::new().unwrap())
+        Self::NamedPipe
     }
 
     fn as_direntry(&self) -> Option<&RwLock<DirEntry>> {
@@ -160,23 +184,23 @@ impl Inner {
         }
     }
 
-    fn as_symlink(&self) -> Option<&SpinLock<String>> {
+    fn as_huge_file(&self) -> Option<&Arc<HugeFile>> {
         match self {
-            Self::SymLink(link) => Some(link),
+            Self::HugeFile(file) => Some(file),
             _ => None,
         }
     }
 
-    fn as_device(&self) -> Option<&Arc<dyn Device>> {
+    fn as_symlink(&self) -> Option<&SpinLock<String>> {
         match self {
-            Self::Device(device) => Some(device),
+            Self::SymLink(link) => Some(link),
             _ => None,
         }
     }
 
-    fn as_named_pipe(&self) -> Option<&NamedPipe> {
+    fn as_device(&self) -> Option<&Arc<dyn Device>> {
         match self {
-            Self::NamedPipe(pipe) => Some(pipe),
+            Self::Device(device) => Some(device),
             _ => None,
         }
     }
@@ -408,7 +432,10 @@ impl RamInode {
 
     fn new_file(fs: &Arc<RamFS>, mode: InodeMode, uid: Uid, gid: Gid) -> Arc<Self> {
         Arc::new_cyclic(|weak_self| RamInode {
-            inner: Inner::new_file(weak_self.clone()),
+            inner: match &fs.huge_subpool {
+                Some(subpool) => Inner::new_huge_file(subpool.clone()),
+                None => Inner::new_file(weak_self.clone()),
+            },
             metadata: SpinLock::new(InodeMeta::new(mode, uid, gid)),
             ino: fs.alloc_id(),
             typ: InodeType::File,
@@ -512,6 +539,11 @@ impl PageCacheBackend for RamInode {
     fn npages(&self) -> usize {
         self.metadata.lock().blocks
     }
+
+    fn is_persistent(&self) -> bool {
+        // The page cache is the only copy of the data.
+        false
+    }
 }
 
 impl Inode for RamInode {
@@ -534,12 +566,12 @@ impl Inode for RamInode {
                     page_cache.pages().read(offset, writer)?;
                     read_len
                 }
+                Inner::HugeFile(file) => file.read_at(offset, writer, self.size())?,
                 Inner::Device(device) => {
                     device.read(writer)?
                     // Typically, devices like "/dev/zero" or "/dev/null" do not require modifying
                     // timestamps here. Please adjust this behavior accordingly if there are special devices.
                 }
-                Inner::NamedPipe(named_pipe) => named_pipe.read(writer)?,
                 _ => return_errno_with_message!(Errno::EISDIR, "read is not supported"),
             }
         };
@@ -557,7 +589,12 @@ impl Inode for RamInode {
     fn write_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
         let written_len = match self.typ {
             InodeType::File => {
-                let page_cache = self.inner.as_file().unwrap();
+                let Some(page_cache) = self.inner.as_file() else {
+                    return_errno_with_message!(
+                        Errno::EINVAL,
+                        "the hugetlbfs files cannot be written"
+                    );
+                };
 
                 let file_size = self.size();
                 let write_len = reader.remain();
@@ -585,10 +622,6 @@ impl Inode for RamInode {
                 // Typically, devices like "/dev/zero" or "/dev/null" do not require modifying
                 // timestamps here. Please adjust this behavior accordingly if there are special devices.
             }
-            InodeType::NamedPipe => {
-                let named_pipe = self.inner.as_named_pipe().unwrap();
-                named_pipe.write(reader)?
-            }
             _ => return_errno_with_message!(Errno::EISDIR, "write is not supported"),
         };
         Ok(written_len)
@@ -612,8 +645,10 @@ impl Inode for RamInode {
             return Ok(());
         }
 
-        let page_cache = self.inner.as_file().unwrap();
-        page_cache.resize(new_size)?;
+        match &self.inner {
+            Inner::HugeFile(file) => file.resize(new_size)?,
+            _ => self.inner.as_file().unwrap().resize(new_size)?,
+        }
 
         let now = now();
         let mut inode_meta = self.metadata.lock();
@@ -778,6 +813,15 @@ impl Inode for RamInode {
         Ok(new_inode)
     }
 
+    fn open(&self) -> Result<Option<Arc<dyn FileIo>>> {
+        // The hugetlbfs files have no page caches, so they are mapped via their own VMOs.
+        let Some(file) = self.inner.as_huge_file() else {
+            return Ok(None);
+        };
+        let inode = self.this.upgrade().unwrap();
+        Ok(Some(Arc::new(HugeFileIo::new(inode, file.clone()))))
+    }
+
     fn readdir_at(&self, offset: usize, visitor: &mut dyn DirentVisitor) -> Result<usize> {
         if self.typ != InodeType::Dir {
             return_errno_with_message!(Errno::ENOTDIR, "self is not dir");
@@ -1116,7 +1160,10 @@ impl Inode for RamInode {
             dev: 0,
             ino: self.ino as _,
             size: inode_metadata.size,
-            blk_size: BLOCK_SIZE,
+            blk_size: self
+                .inner
+                .as_huge_file()
+                .map_or(BLOCK_SIZE, |file| file.page_size()),
             blocks: inode_metadata.blocks,
             atime: inode_metadata.atime,
             mtime: inode_metadata.mtime,
@@ -1171,7 +1218,10 @@ impl Inode for RamInode {
                 }
                 let range = offset..file_size.min(offset + len);
                 // TODO: Think of a more light-weight approach
-                self.inner.as_file().unwrap().fill_zeros(range)
+                match &self.inner {
+                    Inner::HugeFile(file) => file.fill_zeros(range),
+                    _ => self.inner.as_file().unwrap().fill_zeros(range),
+                }
             }
             _ => {
                 return_errno_with_message!(
@@ -0,0 +1,156 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The files of hugetlbfs, which are backed by huge pages.
+//!
+//! Like Linux, the files cannot be written with `write`. They are sized with `ftruncate` or
+//! `fallocate` in multiples of the huge page size, or extended by `mmap`, and accessed through
+//! the memory mappings. The huge pages are allocated when the file grows, so the failure to get
+//! them is reported by the call that grows the file instead of a page fault.
+
+use core::ops::Range;
+
+use align_ext::AlignExt;
+use aster_rights::Rights;
+use ostd::mm::VmIo;
+
+use crate::{
+    events::IoEvents,
+    fs::{inode_handle::FileIo, utils::Inode},
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+    vm::{
+        hugetlb::HugePageSubpool,
+        vmo::{Vmo, VmoFlags, VmoOptions},
+    },
+};
+
+/// The huge pages of a file in hugetlbfs.
+pub(super) struct HugeFile {
+    /// The VMO that holds the huge pages, whose size is always aligned to the huge page size.
+    vmo: Mutex<Vmo<Rights>>,
+    subpool: Arc<HugePageSubpool>,
+}
+
+impl HugeFile {
+    pub(super) fn new(subpool: Arc<HugePageSubpool>) -> Self {
+        let vmo = VmoOptions::<Rights>::new(0)
+            .flags(VmoFlags::RESIZABLE)
+            .alloc()
+            .unwrap();
+        Self {
+            vmo: Mutex::new(vmo),
+            subpool,
+        }
+    }
+
+    pub(super) fn page_size(&self) -> usize {
+        self.subpool.pool().page_size()
+    }
+
+    /// Resizes the file, allocating the huge pages if it grows.
+    pub(super) fn resize(&self, new_size: usize) -> Result<()> {
+        if new_size % self.page_size() != 0 {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the size of a hugetlbfs file must be aligned to the huge page size"
+            );
+        }
+
+        let vmo = self.vmo.lock();
+        let old_size = vmo.size();
+        if new_size <= old_size {
+            return vmo.resize(new_size);
+        }
+
+        let pool = self.subpool.pool();
+        let frames = pool.alloc_pages(
+            (new_size - old_size) / self.page_size(),
+            Some(&self.subpool),
+        )?;
+        vmo.resize(new_size)?;
+        for (i, frame) in frames.into_iter().enumerate() {
+            vmo.replace(frame, old_size / PAGE_SIZE + i)?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn read_at(
+        &self,
+        offset: usize,
+        writer: &mut VmWriter,
+        file_size: usize,
+    ) -> Result<usize> {
+        let start = file_size.min(offset);
+        let end = file_size.min(offset + writer.avail());
+        self.vmo.lock().read(start, writer)?;
+        Ok(end - start)
+    }
+
+    pub(super) fn fill_zeros(&self, range: Range<usize>) -> Result<()> {
+        self.vmo.lock().clear(range)
+    }
+
+    fn vmo(&self) -> Result<Vmo<Rights>> {
+        self.vmo.lock().dup()
+    }
+}
+
+/// An opened file in hugetlbfs.
+pub(super) struct HugeFileIo {
+    inode: Arc<dyn Inode>,
+    file: Arc<HugeFile>,
+}
+
+impl HugeFileIo {
+    pub(super) fn new(inode: Arc<dyn Inode>, file: Arc<HugeFile>) -> Self {
+        Self { inode, file }
+    }
+}
+
+impl Pollable for HugeFileIo {
+    fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        let events = IoEvents::IN | IoEvents::OUT;
+        events & mask
+    }
+}
+
+impl FileIo for HugeFileIo {
+    fn read(&self, writer: &mut VmWriter) -> Result<usize> {
+        self.read_at(0, writer)
+    }
+
+    fn write(&self, _reader: &mut VmReader) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "the hugetlbfs files cannot be written");
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        true
+    }
+
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        self.inode.read_at(offset, writer)
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        self.write(reader)
+    }
+
+    fn mmap_vmo(&self, offset: usize, len: usize) -> Result<(Vmo, usize)> {
+        if offset % self.file.page_size() != 0 {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the offset must be aligned to the huge page size"
+            );
+        }
+
+        // Like Linux, the file is extended to cover the mapping.
+        let end = offset
+            .checked_add(len.align_up(self.file.page_size()))
+            .ok_or_else(|| Error::with_message(Errno::EOVERFLOW, "the mapping is too large"))?;
+        if end > self.inode.size() {
+            self.inode.resize(end)?;
+        }
+
+        Ok((self.file.vmo()?, offset))
+    }
+}
@@ -1,13 +1,17 @@
 // SPDX-License-Identifier: MPL-2.0
 
 //! Ramfs based on PageCache
+//!
+//! The ramfs also serves as hugetlbfs, whose regular files are backed by huge pages.
 
 pub use fs::RamFS;
 
 mod fs;
+mod huge_file;
 mod xattr;
 
 const RAMFS_MAGIC: u64 = 0x0102_1994;
+const HUGETLBFS_MAGIC: u64 = 0x9584_58f6;
 const BLOCK_SIZE: usize = 4096;
 const ROOT_INO: u64 = 1;
 const NAME_MAX: usize = 255;
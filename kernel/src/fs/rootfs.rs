@@ -7,11 +7,13 @@ use libflate::gzip::Decoder as GZipDecoder;
 use spin::Once;
 
 use super::{
+    cgroupfs,
     fs_resolver::{FsPath, FsResolver},
     path::MountNode,
     procfs::{self, ProcFS},
     ramfs::RamFS,
     sysfs::{init as sysfs_init, singleton as sysfs_singleton},
+    tracefs,
     utils::{FileSystem, InodeMode, InodeType},
 };
 use crate::{fs::path::is_dot, prelude::*};
@@ -117,8 +119,19 @@ pub fn init(initramfs_buf: &[u8]) -> Result<()> {
     // Mount SysFS
     let sys_dentry = fs.lookup(&FsPath::try_from("/sys")?)?;
     sysfs_init();
+    cgroupfs::init();
+    tracefs::init();
     let sysfs: Arc<dyn FileSystem> = sysfs_singleton().clone();
     sys_dentry.mount(sysfs)?;
+    // Mount CgroupFS
+    let cgroup_dentry = fs.lookup(&FsPath::try_from("/sys/fs/cgroup")?)?;
+    cgroup_dentry.mount(cgroupfs::singleton().clone())?;
+    // Mount TraceFS
+    let tracing_dentry = fs.lookup(&FsPath::try_from("/sys/kernel/tracing")?)?;
+    tracing_dentry.mount(tracefs::singleton().clone())?;
+    // Mount DebugFS
+    let debug_dentry = fs.lookup(&FsPath::try_from("/sys/kernel/debug")?)?;
+    debug_dentry.mount(RamFS::new())?;
     println!("[kernel] rootfs is ready");
 
     Ok(())
@@ -4,6 +4,7 @@ use alloc::{
     borrow::Cow,
     string::{String, ToString},
     sync::{Arc, Weak},
+    vec,
     vec::Vec,
 };
 use core::time::Duration;
@@ -12,7 +13,10 @@ use aster_systree::{
     SysAttr, SysAttrFlags, SysBranchNode, SysNode, SysNodeId, SysNodeType, SysObj, SysStr,
     SysSymlink, SysTree,
 };
-use ostd::sync::RwLock;
+use ostd::{
+    mm::{FallibleVmWrite, PAGE_SIZE},
+    sync::RwLock,
+};
 
 use crate::{
     events::IoEvents,
@@ -373,16 +377,21 @@ impl Inode for SysFsInode {
         self.read_direct_at(offset, buf)
     }
 
-    fn read_direct_at(&self, _offset: usize, buf: &mut VmWriter) -> Result<usize> {
-        // TODO: it is unclear whether we should simply ignore the offset
-        // or report errors if it is non-zero.
-
+    fn read_direct_at(&self, offset: usize, buf: &mut VmWriter) -> Result<usize> {
         let InnerNode::Attr(attr, leaf) = &self.inner_node else {
             return Err(Error::new(Errno::EINVAL));
         };
 
+        // The attribute is always read as a whole, so that the reads at non-zero offsets (e.g.,
+        // the second read of `cat`) see the end of the file.
+        let mut attr_buf = vec![0u8; PAGE_SIZE];
         // TODO: check read permission
-        Ok(leaf.read_attr(attr.name(), buf)?)
+        let attr_len = leaf.read_attr(attr.name(), &mut VmWriter::from(attr_buf.as_mut_slice()))?;
+        if offset >= attr_len {
+            return Ok(0);
+        }
+
+        Ok(buf.write_fallible(&mut VmReader::from(&attr_buf[offset..attr_len]))?)
     }
 
     fn write_at(&self, offset: usize, buf: &mut VmReader) -> Result<usize> {
@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use super::{inode::TraceInode, node::TraceDir};
+use crate::{
+    fs::utils::{FileSystem, FsFlags, Inode, SuperBlock, NAME_MAX},
+    prelude::*,
+};
+
+/// The trace file system.
+///
+/// The file system has the interface files to enable the tracepoints and read the recorded
+/// events, like the tracefs of Linux.
+pub struct TraceFs {
+    sb: SuperBlock,
+    root: Arc<TraceInode>,
+    inode_allocator: AtomicU64,
+}
+
+const MAGIC_NUMBER: u64 = 0x74726163; // TRACEFS_MAGIC
+pub(super) const BLOCK_SIZE: usize = 4096;
+const ROOT_INO: u64 = 1;
+
+impl TraceFs {
+    pub(super) fn new() -> Arc<Self> {
+        Arc::new_cyclic(|weak_fs| Self {
+            sb: SuperBlock::new(MAGIC_NUMBER, BLOCK_SIZE, NAME_MAX),
+            root: TraceInode::new_dir(TraceDir::Root, ROOT_INO, weak_fs.clone(), None),
+            inode_allocator: AtomicU64::new(ROOT_INO + 1),
+        })
+    }
+
+    pub(super) fn alloc_id(&self) -> u64 {
+        self.inode_allocator.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+impl FileSystem for TraceFs {
+    fn sync(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+
+    fn sb(&self) -> SuperBlock {
+        self.sb.clone()
+    }
+
+    fn flags(&self) -> FsFlags {
+        FsFlags::empty()
+    }
+}
@@ -0,0 +1,353 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use super::{
+    fs::{TraceFs, BLOCK_SIZE},
+    node::{TraceDir, TraceFile, TraceNode},
+};
+use crate::{
+    events::IoEvents,
+    fs::{
+        inode_handle::FileIo,
+        path::{is_dot, is_dotdot},
+        utils::{DirentVisitor, FileSystem, Inode, InodeMode, InodeType, Metadata},
+    },
+    prelude::*,
+    process::{
+        signal::{PollHandle, Pollable},
+        Gid, Uid,
+    },
+};
+
+/// An inode of the trace file system.
+pub struct TraceInode {
+    kind: InodeKind,
+    metadata: RwLock<Metadata>,
+    fs: Weak<TraceFs>,
+    this: Weak<TraceInode>,
+}
+
+enum InodeKind {
+    Dir {
+        dir: TraceDir,
+        parent: Option<Weak<TraceInode>>,
+        /// The inodes that have been looked up, which are cached to keep their inode numbers.
+        cached_children: Mutex<BTreeMap<String, Arc<TraceInode>>>,
+    },
+    File(TraceFile),
+}
+
+impl TraceInode {
+    pub(super) fn new_dir(
+        dir: TraceDir,
+        ino: u64,
+        fs: Weak<TraceFs>,
+        parent: Option<Weak<TraceInode>>,
+    ) -> Arc<Self> {
+        let kind = InodeKind::Dir {
+            dir,
+            parent,
+            cached_children: Mutex::new(BTreeMap::new()),
+        };
+        let metadata = Metadata::new_dir(ino, InodeMode::from_bits_truncate(0o755), BLOCK_SIZE);
+        Self::new(kind, metadata, fs)
+    }
+
+    fn new_file(file: TraceFile, ino: u64, fs: Weak<TraceFs>) -> Arc<Self> {
+        let mode = if file.is_writable() { 0o644 } else { 0o444 };
+        let metadata = Metadata::new_file(ino, InodeMode::from_bits_truncate(mode), BLOCK_SIZE);
+        Self::new(InodeKind::File(file), metadata, fs)
+    }
+
+    fn new(kind: InodeKind, metadata: Metadata, fs: Weak<TraceFs>) -> Arc<Self> {
+        Arc::new_cyclic(|this| Self {
+            kind,
+            metadata: RwLock::new(metadata),
+            fs,
+            this: this.clone(),
+        })
+    }
+
+    fn this(&self) -> Arc<TraceInode> {
+        self.this.upgrade().unwrap()
+    }
+
+    fn alloc_id(&self) -> u64 {
+        self.fs.upgrade().unwrap().alloc_id()
+    }
+
+    /// Returns the inode of a child, which is created if it has not been cached.
+    fn child(&self, name: &str) -> Result<Arc<TraceInode>> {
+        let InodeKind::Dir {
+            dir,
+            cached_children,
+            ..
+        } = &self.kind
+        else {
+            return_errno_with_message!(Errno::ENOTDIR, "the inode is not a directory");
+        };
+
+        let mut cached_children = cached_children.lock();
+        if let Some(inode) = cached_children.get(name) {
+            return Ok(inode.clone());
+        }
+
+        let inode = match dir.child(name) {
+            Some(TraceNode::Dir(child_dir)) => Self::new_dir(
+                child_dir,
+                self.alloc_id(),
+                self.fs.clone(),
+                Some(self.this.clone()),
+            ),
+            Some(TraceNode::File(file)) => Self::new_file(file, self.alloc_id(), self.fs.clone()),
+            None => return_errno_with_message!(Errno::ENOENT, "the file does not exist"),
+        };
+        cached_children.insert(name.to_string(), inode.clone());
+        Ok(inode)
+    }
+
+    fn parent(&self) -> Option<Arc<TraceInode>> {
+        match &self.kind {
+            InodeKind::Dir { parent, .. } => parent.as_ref().and_then(Weak::upgrade),
+            InodeKind::File(_) => None,
+        }
+    }
+}
+
+impl Inode for TraceInode {
+    fn size(&self) -> usize {
+        self.metadata.read().size
+    }
+
+    fn resize(&self, new_size: usize) -> Result<()> {
+        let InodeKind::File(file) = &self.kind else {
+            return_errno_with_message!(Errno::EISDIR, "the inode is a directory");
+        };
+
+        // Writing to a trace file with `O_TRUNC` is allowed.
+        if new_size == 0 {
+            file.truncate();
+        }
+        Ok(())
+    }
+
+    fn metadata(&self) -> Metadata {
+        *self.metadata.read()
+    }
+
+    fn ino(&self) -> u64 {
+        self.metadata.read().ino
+    }
+
+    fn type_(&self) -> InodeType {
+        self.metadata.read().type_
+    }
+
+    fn mode(&self) -> Result<InodeMode> {
+        Ok(self.metadata.read().mode)
+    }
+
+    fn set_mode(&self, mode: InodeMode) -> Result<()> {
+        self.metadata.write().mode = mode;
+        Ok(())
+    }
+
+    fn owner(&self) -> Result<Uid> {
+        Ok(self.metadata.read().uid)
+    }
+
+    fn set_owner(&self, uid: Uid) -> Result<()> {
+        self.metadata.write().uid = uid;
+        Ok(())
+    }
+
+    fn group(&self) -> Result<Gid> {
+        Ok(self.metadata.read().gid)
+    }
+
+    fn set_group(&self, gid: Gid) -> Result<()> {
+        self.metadata.write().gid = gid;
+        Ok(())
+    }
+
+    fn atime(&self) -> Duration {
+        self.metadata.read().atime
+    }
+
+    fn set_atime(&self, time: Duration) {
+        self.metadata.write().atime = time;
+    }
+
+    fn mtime(&self) -> Duration {
+        self.metadata.read().mtime
+    }
+
+    fn set_mtime(&self, time: Duration) {
+        self.metadata.write().mtime = time;
+    }
+
+    fn ctime(&self) -> Duration {
+        self.metadata.read().ctime
+    }
+
+    fn set_ctime(&self, time: Duration) {
+        self.metadata.write().ctime = time;
+    }
+
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let InodeKind::File(file) = &self.kind else {
+            return_errno_with_message!(Errno::EISDIR, "the inode is a directory");
+        };
+
+        let output = file.read();
+        let Some(data) = output.as_bytes().get(offset..) else {
+            return Ok(0);
+        };
+        Ok(writer.write_fallible(&mut data.into())?)
+    }
+
+    fn read_direct_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        self.read_at(offset, writer)
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let InodeKind::File(file) = &self.kind else {
+            return_errno_with_message!(Errno::EISDIR, "the inode is a directory");
+        };
+
+        let len = reader.remain();
+        if len > PAGE_SIZE {
+            return_errno_with_message!(Errno::EINVAL, "the written contents are too long");
+        }
+        let mut buffer = vec![0u8; len];
+        reader
+            .read_fallible(&mut VmWriter::from(buffer.as_mut_slice()))
+            .map_err(|(err, _)| err)?;
+        let Ok(input) = core::str::from_utf8(&buffer) else {
+            return_errno_with_message!(Errno::EINVAL, "the written contents are not UTF-8");
+        };
+
+        file.write(input)?;
+        Ok(len)
+    }
+
+    fn write_direct_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        self.write_at(offset, reader)
+    }
+
+    fn open(&self) -> Result<Option<Arc<dyn FileIo>>> {
+        match &self.kind {
+            // The events keep changing, so each opened `trace` file reads a snapshot of them.
+            InodeKind::File(file @ TraceFile::Trace) => {
+                Ok(Some(Arc::new(SnapshotFile::new(*file))))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn readdir_at(&self, offset: usize, visitor: &mut dyn DirentVisitor) -> Result<usize> {
+        let InodeKind::Dir { dir, .. } = &self.kind else {
+            return_errno_with_message!(Errno::ENOTDIR, "the inode is not a directory");
+        };
+
+        // The entries are listed by index: `.`, `..`, and the children.
+        let mut names = vec![".", ".."];
+        names.extend(dir.children().iter().map(|(name, _)| *name));
+
+        let mut current_offset = offset;
+        for name in names.iter().skip(offset) {
+            let inode = if is_dot(name) {
+                self.this()
+            } else if is_dotdot(name) {
+                self.parent().unwrap_or_else(|| self.this())
+            } else {
+                self.child(name)?
+            };
+
+            if visitor
+                .visit(name, inode.ino(), inode.type_(), current_offset + 1)
+                .is_err()
+            {
+                break;
+            }
+            current_offset += 1;
+        }
+
+        if current_offset == offset && offset < names.len() {
+            return_errno_with_message!(Errno::EINVAL, "the buffer is too small");
+        }
+        Ok(current_offset - offset)
+    }
+
+    fn unlink(&self, _name: &str) -> Result<()> {
+        return_errno_with_message!(Errno::EPERM, "the trace files cannot be removed");
+    }
+
+    fn rmdir(&self, _name: &str) -> Result<()> {
+        return_errno_with_message!(Errno::EPERM, "the trace directories cannot be removed");
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>> {
+        if is_dot(name) {
+            return Ok(self.this());
+        }
+        if is_dotdot(name) {
+            return Ok(self.parent().unwrap_or_else(|| self.this()));
+        }
+
+        Ok(self.child(name)?)
+    }
+
+    fn fs(&self) -> Arc<dyn FileSystem> {
+        self.fs.upgrade().unwrap()
+    }
+}
+
+/// An opened trace file whose contents are generated at the first read and kept afterward.
+struct SnapshotFile {
+    file: TraceFile,
+    contents: Mutex<Option<String>>,
+}
+
+impl SnapshotFile {
+    fn new(file: TraceFile) -> Self {
+        Self {
+            file,
+            contents: Mutex::new(None),
+        }
+    }
+}
+
+impl Pollable for SnapshotFile {
+    fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        let events = IoEvents::IN | IoEvents::OUT;
+        events & mask
+    }
+}
+
+impl FileIo for SnapshotFile {
+    fn read(&self, writer: &mut VmWriter) -> Result<usize> {
+        self.read_at(0, writer)
+    }
+
+    fn write(&self, reader: &mut VmReader) -> Result<usize> {
+        // The written contents are ignored, as in `TraceFile::write`.
+        let len = reader.remain();
+        reader.skip(len);
+        Ok(len)
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        true
+    }
+
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let mut contents = self.contents.lock();
+        let contents = contents.get_or_insert_with(|| self.file.read());
+        let Some(data) = contents.as_bytes().get(offset..) else {
+            return Ok(0);
+        };
+        Ok(writer.write_fallible(&mut data.into())?)
+    }
+}
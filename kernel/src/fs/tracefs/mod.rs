@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The trace file system.
+//!
+//! The file system exposes the tracepoints of the kernel (see [`aster_trace`]) to the user
+//! space. It is mounted at `/sys/kernel/tracing` during the boot and can also be mounted
+//! elsewhere with the `tracefs` type. The files are a subset of those in Linux:
+//! - `available_events` lists the tracepoints as `<subsystem>:<name>`;
+//! - `set_event` lists the enabled tracepoints, and a tracepoint is enabled by writing its
+//!   name or disabled by writing its name prefixed with `!`;
+//! - `events/<subsystem>/<name>/enable` tells or changes whether a tracepoint is enabled;
+//! - `events/<subsystem>/<name>/format` describes the arguments of a tracepoint;
+//! - `tracing_on` tells or changes whether the enabled tracepoints record events;
+//! - `trace` shows the recorded events, which are cleared if the file is opened with
+//!   `O_TRUNC`.
+
+mod fs;
+mod inode;
+mod node;
+
+use spin::Once;
+
+pub use self::{fs::TraceFs, inode::TraceInode};
+use super::cgroupfs::MountPointNode;
+use crate::prelude::*;
+
+static TRACEFS_SINGLETON: Once<Arc<TraceFs>> = Once::new();
+
+/// Returns a reference to the global trace file system. Panics if not initialized.
+pub fn singleton() -> &'static Arc<TraceFs> {
+    TRACEFS_SINGLETON.get().expect("TraceFs not initialized")
+}
+
+/// Initializes the trace file system.
+///
+/// This also creates the `/sys/kernel/tracing` directory in sysfs as the mount point, together
+/// with `/sys/kernel/debug` for the debug files (e.g., `kcov`) and `/sys/kernel/mm` for the
+/// memory management knobs (e.g., KSM and huge pages). So it should be called *after*
+/// `aster_systree::init()`.
+pub fn init() {
+    TRACEFS_SINGLETON.call_once(|| {
+        let kernel_node = MountPointNode::new("kernel");
+        kernel_node
+            .add_child(MountPointNode::new("tracing"))
+            .unwrap();
+        kernel_node.add_child(MountPointNode::new("debug")).unwrap();
+        let mm_node = MountPointNode::new("mm");
+        mm_node
+            .add_child(crate::vm::vmar::ksm::new_sys_node())
+            .unwrap();
+        let hugepages_node = MountPointNode::new("hugepages");
+        for pool_node in crate::vm::hugetlb::new_sys_nodes() {
+            hugepages_node.add_child(pool_node).unwrap();
+        }
+        mm_node.add_child(hugepages_node).unwrap();
+        kernel_node.add_child(mm_node).unwrap();
+        aster_systree::singleton()
+            .root()
+            .add_child(kernel_node)
+            .unwrap();
+
+        TraceFs::new()
+    });
+}
@@ -0,0 +1,217 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::fmt::Write;
+
+use aster_trace::{ArgFormat, Tracepoint};
+
+use crate::prelude::*;
+
+/// A directory of the trace file system.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum TraceDir {
+    /// The root directory.
+    Root,
+    /// The directory of all the subsystems (`events`).
+    Events,
+    /// The directory of the tracepoints in a subsystem (`events/<subsystem>`).
+    Subsystem(&'static str),
+    /// The directory of a tracepoint (`events/<subsystem>/<name>`).
+    Event(&'static Tracepoint),
+}
+
+/// A directory or a file of the trace file system.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum TraceNode {
+    Dir(TraceDir),
+    File(TraceFile),
+}
+
+impl TraceDir {
+    /// Returns the names and the nodes of the children.
+    pub(super) fn children(&self) -> Vec<(&'static str, TraceNode)> {
+        match self {
+            Self::Root => {
+                let mut children = TraceFile::ROOT_FILES
+                    .iter()
+                    .map(|file| (file.name(), TraceNode::File(*file)))
+                    .collect::<Vec<_>>();
+                children.push(("events", TraceNode::Dir(Self::Events)));
+                children
+            }
+            Self::Events => {
+                let mut subsystems = aster_trace::all_tracepoints()
+                    .iter()
+                    .map(|tracepoint| tracepoint.subsystem())
+                    .collect::<Vec<_>>();
+                subsystems.dedup();
+                subsystems
+                    .into_iter()
+                    .map(|subsystem| (subsystem, TraceNode::Dir(Self::Subsystem(subsystem))))
+                    .collect()
+            }
+            Self::Subsystem(subsystem) => aster_trace::all_tracepoints()
+                .into_iter()
+                .filter(|tracepoint| tracepoint.subsystem() == *subsystem)
+                .map(|tracepoint| (tracepoint.name(), TraceNode::Dir(Self::Event(tracepoint))))
+                .collect(),
+            Self::Event(tracepoint) => vec![
+                ("enable", TraceNode::File(TraceFile::Enable(tracepoint))),
+                ("format", TraceNode::File(TraceFile::Format(tracepoint))),
+            ],
+        }
+    }
+
+    /// Returns the child with the given name.
+    pub(super) fn child(&self, name: &str) -> Option<TraceNode> {
+        self.children()
+            .into_iter()
+            .find(|(child_name, _)| *child_name == name)
+            .map(|(_, node)| node)
+    }
+}
+
+/// An interface file of the trace file system.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum TraceFile {
+    /// The tracepoints that can be enabled (`available_events`).
+    AvailableEvents,
+    /// The enabled tracepoints (`set_event`).
+    SetEvent,
+    /// Whether the enabled tracepoints record events (`tracing_on`).
+    TracingOn,
+    /// The recorded events (`trace`).
+    Trace,
+    /// Whether a tracepoint is enabled (`events/<subsystem>/<name>/enable`).
+    Enable(&'static Tracepoint),
+    /// The arguments of a tracepoint (`events/<subsystem>/<name>/format`).
+    Format(&'static Tracepoint),
+}
+
+impl TraceFile {
+    const ROOT_FILES: [Self; 4] = [
+        Self::AvailableEvents,
+        Self::SetEvent,
+        Self::Trace,
+        Self::TracingOn,
+    ];
+
+    pub(super) fn name(&self) -> &'static str {
+        match self {
+            Self::AvailableEvents => "available_events",
+            Self::SetEvent => "set_event",
+            Self::TracingOn => "tracing_on",
+            Self::Trace => "trace",
+            Self::Enable(_) => "enable",
+            Self::Format(_) => "format",
+        }
+    }
+
+    pub(super) fn is_writable(&self) -> bool {
+        !matches!(self, Self::AvailableEvents | Self::Format(_))
+    }
+
+    /// Generates the contents of the file.
+    pub(super) fn read(&self) -> String {
+        let mut output = String::new();
+        match self {
+            Self::AvailableEvents => {
+                for tracepoint in aster_trace::all_tracepoints() {
+                    writeln!(output, "{}:{}", tracepoint.subsystem(), tracepoint.name()).unwrap();
+                }
+            }
+            Self::SetEvent => {
+                for tracepoint in aster_trace::all_tracepoints() {
+                    if tracepoint.is_enabled() {
+                        writeln!(output, "{}:{}", tracepoint.subsystem(), tracepoint.name())
+                            .unwrap();
+                    }
+                }
+            }
+            Self::TracingOn => {
+                writeln!(output, "{}", aster_trace::is_tracing_on() as u8).unwrap();
+            }
+            Self::Trace => {
+                let events = aster_trace::read_events();
+                let nr_lost = aster_trace::num_lost_events();
+                writeln!(output, "# tracer: nop").unwrap();
+                writeln!(output, "#").unwrap();
+                writeln!(
+                    output,
+                    "# entries-in-buffer/entries-written: {}/{}   #P:{}",
+                    events.len(),
+                    events.len() as u64 + nr_lost,
+                    ostd::cpu::num_cpus()
+                )
+                .unwrap();
+                writeln!(output, "#").unwrap();
+                writeln!(output, "#  CPU     TIMESTAMP  EVENT").unwrap();
+                for event in events.iter() {
+                    writeln!(output, "{}", event).unwrap();
+                }
+            }
+            Self::Enable(tracepoint) => {
+                writeln!(output, "{}", tracepoint.is_enabled() as u8).unwrap();
+            }
+            Self::Format(tracepoint) => {
+                writeln!(output, "name: {}", tracepoint.name()).unwrap();
+                writeln!(output, "subsystem: {}", tracepoint.subsystem()).unwrap();
+                writeln!(output, "format:").unwrap();
+                for arg in tracepoint.args() {
+                    let format = match arg.format() {
+                        ArgFormat::Unsigned => "unsigned",
+                        ArgFormat::Signed => "signed",
+                        ArgFormat::Hex => "hex",
+                    };
+                    writeln!(output, "\t{}: {}", arg.name(), format).unwrap();
+                }
+            }
+        }
+        output
+    }
+
+    /// Parses the written contents and applies them.
+    pub(super) fn write(&self, input: &str) -> Result<()> {
+        match self {
+            Self::AvailableEvents | Self::Format(_) => {
+                return_errno_with_message!(Errno::EACCES, "the file is read-only");
+            }
+            Self::SetEvent => {
+                for pattern in input.split_whitespace() {
+                    let (pattern, is_enabled) = match pattern.strip_prefix('!') {
+                        Some(pattern) => (pattern, false),
+                        None => (pattern, true),
+                    };
+                    if aster_trace::set_tracepoints_enabled(pattern, is_enabled) == 0 {
+                        return_errno_with_message!(Errno::EINVAL, "no tracepoint matches");
+                    }
+                }
+            }
+            Self::TracingOn => aster_trace::set_tracing_on(parse_bool(input)?),
+            // Like Linux, the written contents are ignored. Opening the file with `O_TRUNC`
+            // clears the events.
+            Self::Trace => (),
+            Self::Enable(tracepoint) => tracepoint.set_enabled(parse_bool(input)?),
+        }
+        Ok(())
+    }
+
+    /// Truncates the file, which happens when the file is opened with `O_TRUNC`.
+    pub(super) fn truncate(&self) {
+        match self {
+            // Like Linux, `echo <event> > set_event` enables only the written tracepoints.
+            Self::SetEvent => {
+                aster_trace::set_tracepoints_enabled("*:*", false);
+            }
+            Self::Trace => aster_trace::clear_events(),
+            _ => (),
+        }
+    }
+}
+
+fn parse_bool(input: &str) -> Result<bool> {
+    match input.trim() {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        _ => return_errno_with_message!(Errno::EINVAL, "the value is neither 0 nor 1"),
+    }
+}
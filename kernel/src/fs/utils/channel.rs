@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 use aster_rights::{Read, ReadOp, TRights, Write, WriteOp};
 use aster_rights_proc::require;
@@ -79,6 +79,30 @@ impl<T> Channel<T> {
     pub fn capacity(&self) -> usize {
         self.producer.0.common.capacity()
     }
+
+    /// Reopens the channel if it has been shut down.
+    ///
+    /// The items that have not been read are kept. This is used by named pipes, whose read and
+    /// write ends can be opened again after all the ends of one side are closed.
+    pub fn reopen(&self) {
+        self.producer.0.common.reopen()
+    }
+}
+
+impl<T: Pod> Channel<T> {
+    /// Sets the capacity of the channel.
+    ///
+    /// # Errors
+    ///
+    /// This method will fail with `EBUSY` if the channel contains more items than the new
+    /// capacity.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the given capacity is not a power of two.
+    pub fn set_capacity(&self, capacity: usize) -> Result<()> {
+        self.producer.0.common.set_capacity(capacity)
+    }
 }
 
 pub struct Producer<T>(Fifo<T, WriteOp>);
@@ -95,6 +119,10 @@ macro_rules! impl_common_methods_for_channel {
             self.0.common.is_shutdown()
         }
 
+        pub fn capacity(&self) -> usize {
+            self.0.common.capacity()
+        }
+
         pub fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
             self.this_end()
                 .pollee
@@ -118,7 +146,8 @@ impl<T> Producer<T> {
 
         if self.is_shutdown() {
             IoEvents::ERR | IoEvents::OUT
-        } else if rb.free_len() > PIPE_BUF {
+        } else if rb.free_len() >= PIPE_BUF {
+            // Any atomic write can succeed.
             IoEvents::OUT
         } else {
             IoEvents::empty()
@@ -155,7 +184,43 @@ impl Producer<u8> {
     }
 }
 
+impl Producer<u8> {
+    /// Tries to write `buf` to the channel as packets.
+    ///
+    /// Each packet has at most `PIPE_BUF` bytes, so `buf` is split into multiple packets if it is
+    /// larger. The reader will read at most one packet at a time, and the remaining bytes of the
+    /// packet that do not fit into the reader's buffer are discarded. This is the packet mode of
+    /// pipes (i.e., `O_DIRECT`).
+    ///
+    /// The error semantics are the same as [`Self::try_write`].
+    pub fn try_write_packets(&self, reader: &mut dyn MultiRead) -> Result<usize> {
+        if reader.is_empty() {
+            return Ok(0);
+        }
+
+        if self.is_shutdown() {
+            return_errno_with_message!(Errno::EPIPE, "the channel is shut down");
+        }
+
+        let written_len = self.0.write_packets(reader)?;
+        self.peer_end().pollee.notify(IoEvents::IN);
+
+        if written_len > 0 {
+            Ok(written_len)
+        } else {
+            return_errno_with_message!(Errno::EAGAIN, "the channel is full");
+        }
+    }
+}
+
 impl<T: Pod> Producer<T> {
+    /// Sets the capacity of the channel.
+    ///
+    /// See [`Channel::set_capacity`] for details.
+    pub fn set_capacity(&self, capacity: usize) -> Result<()> {
+        self.0.common.set_capacity(capacity)
+    }
+
     /// Tries to push `item` to the channel.
     ///
     /// - Returns `Ok(())` if successful.
@@ -216,6 +281,17 @@ impl Consumer<u8> {
     /// - Returns `Ok(0)` if the channel is shut down and there is no data left.
     /// - Returns `Err(EAGAIN)` if the channel is empty.
     pub fn try_read(&self, writer: &mut dyn MultiWrite) -> Result<usize> {
+        self.try_read_with_max_len(writer, usize::MAX)
+    }
+
+    /// Tries to read at most `max_len` bytes from the channel.
+    ///
+    /// The error semantics are the same as [`Self::try_read`].
+    pub fn try_read_with_max_len(
+        &self,
+        writer: &mut dyn MultiWrite,
+        max_len: usize,
+    ) -> Result<usize> {
         if writer.is_empty() {
             return Ok(0);
         }
@@ -223,7 +299,7 @@ impl Consumer<u8> {
         // This must be recorded before the actual operation to avoid race conditions.
         let is_shutdown = self.is_shutdown();
 
-        let read_len = self.0.read(writer)?;
+        let read_len = self.0.read(writer, max_len)?;
         self.peer_end().pollee.notify(IoEvents::OUT);
         self.this_end().pollee.invalidate();
 
@@ -238,6 +314,13 @@ impl Consumer<u8> {
 }
 
 impl<T: Pod> Consumer<T> {
+    /// Sets the capacity of the channel.
+    ///
+    /// See [`Channel::set_capacity`] for details.
+    pub fn set_capacity(&self, capacity: usize) -> Result<()> {
+        self.0.common.set_capacity(capacity)
+    }
+
     /// Tries to read an item from the channel.
     ///
     /// - Returns `Ok(Some(_))` with the popped item if successful.
@@ -283,9 +366,36 @@ impl<T, R: TRights> Fifo<T, R> {
 
 impl<R: TRights> Fifo<u8, R> {
     #[require(R > Read)]
-    pub fn read(&self, writer: &mut dyn MultiWrite) -> Result<usize> {
+    pub fn read(&self, writer: &mut dyn MultiWrite, max_len: usize) -> Result<usize> {
         let mut rb = self.common.consumer.rb();
-        rb.read_fallible(writer)
+        let mut packets = self.common.packets.lock();
+        let nr_read = self.common.nr_read.load(Ordering::Relaxed);
+
+        let read_len = match packets.front() {
+            // The data at the head is a packet, which is read at most once.
+            Some(packet) if packet.start == nr_read => {
+                let packet_len = packet.len;
+                let read_len = rb.read_fallible_with_max_len(writer, packet_len.min(max_len))?;
+                // The rest of the packet is discarded.
+                rb.skip(packet_len - read_len).unwrap();
+                packets.pop_front();
+                self.common
+                    .nr_read
+                    .store(nr_read.wrapping_add(packet_len), Ordering::Relaxed);
+                return Ok(read_len);
+            }
+            // The data at the head is a byte stream, which cannot be read across the packet.
+            Some(packet) => rb.read_fallible_with_max_len(
+                writer,
+                packet.start.wrapping_sub(nr_read).min(max_len),
+            )?,
+            None => rb.read_fallible_with_max_len(writer, max_len)?,
+        };
+        self.common
+            .nr_read
+            .store(nr_read.wrapping_add(read_len), Ordering::Relaxed);
+
+        Ok(read_len)
     }
 
     #[require(R > Write)]
@@ -295,7 +405,46 @@ impl<R: TRights> Fifo<u8, R> {
             // No sufficient space for an atomic write
             return Ok(0);
         }
-        rb.write_fallible(reader)
+        let written_len = rb.write_fallible(reader)?;
+        self.common
+            .nr_written
+            .fetch_add(written_len, Ordering::Relaxed);
+
+        Ok(written_len)
+    }
+
+    #[require(R > Write)]
+    pub fn write_packets(&self, reader: &mut dyn MultiRead) -> Result<usize> {
+        let mut rb = self.common.producer.rb();
+        // The lock must be held while the data are being written. Otherwise, the reader may read
+        // the data of a packet as a byte stream before the packet is recorded.
+        let mut packets = self.common.packets.lock();
+
+        let mut written_len = 0;
+        while !reader.is_empty() {
+            // Each packet is written atomically.
+            let len = reader.sum_lens().min(PIPE_BUF);
+            if rb.free_len() < len {
+                break;
+            }
+
+            let packet_len = match rb.write_fallible_with_max_len(reader, len) {
+                Ok(packet_len) => packet_len,
+                Err(_) if written_len > 0 => break,
+                Err(err) => return Err(err),
+            };
+            let start = self
+                .common
+                .nr_written
+                .fetch_add(packet_len, Ordering::Relaxed);
+            packets.push_back(Packet {
+                start,
+                len: packet_len,
+            });
+            written_len += packet_len;
+        }
+
+        Ok(written_len)
     }
 }
 
@@ -321,6 +470,19 @@ struct Common<T> {
     producer: FifoInner<RbProducer<T>>,
     consumer: FifoInner<RbConsumer<T>>,
     is_shutdown: AtomicBool,
+    /// The packets that have not been read.
+    packets: Mutex<VecDeque<Packet>>,
+    /// The total number of items that have been written.
+    nr_written: AtomicUsize,
+    /// The total number of items that have been read or discarded.
+    nr_read: AtomicUsize,
+}
+
+/// A packet written by [`Producer::try_write_packets`].
+struct Packet {
+    /// The offset of the packet in all the bytes written to the channel.
+    start: usize,
+    len: usize,
 }
 
 impl<T> Common<T> {
@@ -350,6 +512,9 @@ impl<T> Common<T> {
             producer,
             consumer,
             is_shutdown: AtomicBool::new(false),
+            packets: Mutex::new(VecDeque::new()),
+            nr_written: AtomicUsize::new(0),
+            nr_read: AtomicUsize::new(0),
         }
     }
 
@@ -373,6 +538,50 @@ impl<T> Common<T> {
         // will fail with an `EPIPE` error).
         self.producer.pollee.notify(IoEvents::ERR | IoEvents::OUT);
     }
+
+    pub fn reopen(&self) {
+        if !self.is_shutdown.swap(false, Ordering::Relaxed) {
+            return;
+        }
+
+        // The events caused by the shutdown are no longer valid.
+        self.consumer.pollee.invalidate();
+        self.producer.pollee.invalidate();
+    }
+}
+
+impl<T: Pod> Common<T> {
+    fn set_capacity(&self, capacity: usize) -> Result<()> {
+        // Both ends are locked so that no data can be moved in or out during the resizing.
+        let mut producer = self.producer.rb();
+        let mut consumer = self.consumer.rb();
+        if producer.capacity() == capacity {
+            return Ok(());
+        }
+
+        let len = consumer.len();
+        if len > capacity {
+            return_errno_with_message!(
+                Errno::EBUSY,
+                "the channel contains more items than the new capacity"
+            );
+        }
+
+        let mut items = vec![T::new_zeroed(); len];
+        consumer.pop_slice(&mut items).unwrap();
+        let (mut new_producer, new_consumer) = RingBuffer::new(capacity).split();
+        new_producer.push_slice(&items).unwrap();
+
+        *producer = new_producer;
+        *consumer = new_consumer;
+        drop(consumer);
+        drop(producer);
+
+        // The writable events may change.
+        self.producer.pollee.notify(IoEvents::OUT);
+
+        Ok(())
+    }
 }
 
 struct FifoInner<T> {
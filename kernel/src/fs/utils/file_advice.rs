@@ -0,0 +1,21 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::prelude::*;
+
+/// The advice on how the data of an opened file will be accessed, which is given by `fadvise`.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromInt)]
+pub enum FileAdvice {
+    /// No special treatment, which is the default.
+    Normal = 0,
+    /// The data will be accessed in random order, so the readahead is disabled.
+    Random = 1,
+    /// The data will be accessed sequentially, so the readahead window is enlarged.
+    Sequential = 2,
+    /// The data will be accessed in the near future.
+    WillNeed = 3,
+    /// The data will not be accessed in the near future, so the clean cached pages are dropped.
+    DontNeed = 4,
+    /// The data will be accessed only once.
+    NoReuse = 5,
+}
@@ -0,0 +1,23 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use bitflags::bitflags;
+
+bitflags! {
+    /// The seals of a file, which restrict the operations that can be performed on the file.
+    ///
+    /// Seals are added by `fcntl(F_ADD_SEALS)` and cannot be removed.
+    pub struct FileSeals: u32 {
+        /// Prevents further seals from being added.
+        const F_SEAL_SEAL = 0x0001;
+        /// Prevents the file from shrinking.
+        const F_SEAL_SHRINK = 0x0002;
+        /// Prevents the file from growing.
+        const F_SEAL_GROW = 0x0004;
+        /// Prevents the file contents from being modified.
+        const F_SEAL_WRITE = 0x0008;
+        /// Like `F_SEAL_WRITE`, but the existing shared writable mappings are kept.
+        const F_SEAL_FUTURE_WRITE = 0x0010;
+        /// Prevents the execute permissions of the file from being changed.
+        const F_SEAL_EXEC = 0x0020;
+    }
+}
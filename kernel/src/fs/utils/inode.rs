@@ -14,9 +14,15 @@ use super::{
 };
 use crate::{
     events::IoEvents,
-    fs::device::{Device, DeviceType},
+    fs::{
+        device::{Device, DeviceType},
+        inode_handle::FileIo,
+    },
     prelude::*,
-    process::{posix_thread::AsPosixThread, signal::PollHandle, Gid, Uid},
+    process::{
+        credentials::capabilities::CapSet, posix_thread::AsPosixThread, signal::PollHandle, Gid,
+        Uid,
+    },
     time::clocks::RealTimeCoarseClock,
     vm::vmo::Vmo,
 };
@@ -84,6 +90,18 @@ impl From<DeviceType> for InodeType {
     }
 }
 
+impl TryFrom<InodeType> for DeviceType {
+    type Error = Error;
+
+    fn try_from(type_: InodeType) -> Result<Self> {
+        match type_ {
+            InodeType::CharDevice => Ok(DeviceType::CharDevice),
+            InodeType::BlockDevice => Ok(DeviceType::BlockDevice),
+            _ => return_errno_with_message!(Errno::EINVAL, "the inode is not a device"),
+        }
+    }
+}
+
 bitflags! {
     pub struct Permission: u16 {
         // This implementation refers the implementation of linux
@@ -413,6 +431,15 @@ pub trait Inode: Any + Sync + Send {
         None
     }
 
+    /// Opens the inode with its own file operations.
+    ///
+    /// An inode that keeps some states per opened file (e.g., the generated content of a procfs
+    /// file) returns the operations here. By default, the file operations are performed on the
+    /// inode directly.
+    fn open(&self) -> Result<Option<Arc<dyn FileIo>>> {
+        Ok(None)
+    }
+
     fn readdir_at(&self, offset: usize, visitor: &mut dyn DirentVisitor) -> Result<usize> {
         Err(Error::new(Errno::ENOTDIR))
     }
@@ -525,6 +552,10 @@ pub trait Inode: Any + Sync + Send {
     ///
     /// Similar to Linux, using "fsuid" here allows setting filesystem permissions
     /// without changing the "normal" uids for other tasks.
+    ///
+    /// If the permission bits do not grant the access, the access is still allowed with the
+    /// `CAP_DAC_OVERRIDE` or `CAP_DAC_READ_SEARCH` capability, if the owner and the group of the
+    /// file are mapped in the user namespace, as in Linux.
     fn check_permission(&self, mut perm: Permission) -> Result<()> {
         let creds = match Task::current() {
             Some(task) => match task.as_posix_thread() {
@@ -539,28 +570,50 @@ pub trait Inode: Any + Sync + Send {
         let metadata = self.metadata();
         let mode = metadata.mode;
 
-        if metadata.uid == creds.fsuid() {
-            if (perm.may_read() && !mode.is_owner_readable())
-                || (perm.may_write() && !mode.is_owner_writable())
-                || (perm.may_exec() && !mode.is_owner_executable())
-            {
-                return_errno_with_message!(Errno::EACCES, "owner permission check failed");
-            }
-        } else if metadata.gid == creds.fsgid() {
-            if (perm.may_read() && !mode.is_group_readable())
-                || (perm.may_write() && !mode.is_group_writable())
-                || (perm.may_exec() && !mode.is_group_executable())
-            {
-                return_errno_with_message!(Errno::EACCES, "group permission check failed");
-            }
-        } else if (perm.may_read() && !mode.is_other_readable())
-            || (perm.may_write() && !mode.is_other_writable())
-            || (perm.may_exec() && !mode.is_other_executable())
+        let (readable, writable, executable) = if metadata.uid == creds.fsuid() {
+            (
+                mode.is_owner_readable(),
+                mode.is_owner_writable(),
+                mode.is_owner_executable(),
+            )
+        } else if creds.is_in_group(metadata.gid) {
+            (
+                mode.is_group_readable(),
+                mode.is_group_writable(),
+                mode.is_group_executable(),
+            )
+        } else {
+            (
+                mode.is_other_readable(),
+                mode.is_other_writable(),
+                mode.is_other_executable(),
+            )
+        };
+        if (!perm.may_read() || readable)
+            && (!perm.may_write() || writable)
+            && (!perm.may_exec() || executable)
         {
-            return_errno_with_message!(Errno::EACCES, "other permission check failed");
+            return Ok(());
         }
 
-        Ok(())
+        let is_dir = metadata.type_ == InodeType::Dir;
+        // `CAP_DAC_OVERRIDE` does not allow executing a file that no one can execute.
+        let is_executable =
+            mode.is_owner_executable() || mode.is_group_executable() || mode.is_other_executable();
+        if creds.has_capability_for_owner(CapSet::DAC_OVERRIDE, metadata.uid, metadata.gid)
+            && (is_dir || !perm.may_exec() || is_executable)
+        {
+            return Ok(());
+        }
+        // `CAP_DAC_READ_SEARCH` allows reading files, and reading and searching directories.
+        if creds.has_capability_for_owner(CapSet::DAC_READ_SEARCH, metadata.uid, metadata.gid)
+            && !perm.may_write()
+            && (is_dir || !perm.may_exec())
+        {
+            return Ok(());
+        }
+
+        return_errno_with_message!(Errno::EACCES, "the permission bits do not allow the access");
     }
 }
 
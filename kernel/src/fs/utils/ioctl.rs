@@ -12,17 +12,29 @@ pub enum IoctlCmd {
     TCSETSW = 0x5403,
     /// Drain the output buffer, and discard pending input, and set attributes
     TCSETSF = 0x5404,
+    /// Send a break or wait until the output is sent
+    TCSBRK = 0x5409,
+    /// Suspend or restart the output or the input
+    TCXONC = 0x540A,
+    /// Discard the input or the output
+    TCFLSH = 0x540B,
     /// Make the given terminal the controlling terminal of the calling process.
     TIOCSCTTY = 0x540e,
     /// Get the process group ID of the foreground process group on this terminal
     TIOCGPGRP = 0x540f,
     /// Set the foreground process group ID of this terminal.
     TIOCSPGRP = 0x5410,
-    /// Get the number of bytes in the input buffer.
+    /// Get the number of bytes in the output buffer.
+    TIOCOUTQ = 0x5411,
+    /// Insert the given byte in the input queue as if it were typed.
+    TIOCSTI = 0x5412,
+    /// Get the number of bytes in the input buffer (also known as `TIOCINQ`).
     FIONREAD = 0x541B,
     /// Set window size
     TIOCGWINSZ = 0x5413,
     TIOCSWINSZ = 0x5414,
+    /// Enable or disable the packet mode of the pseudoterminal master.
+    TIOCPKT = 0x5420,
     /// Enable or disable non-blocking I/O mode.
     FIONBIO = 0x5421,
     /// the calling process gives up this controlling terminal
@@ -35,12 +47,80 @@ pub enum IoctlCmd {
     FIOCLEX = 0x5451,
     /// Enable or disable asynchronous I/O mode.
     FIOASYNC = 0x5452,
+    /// Check whether the socket is at the out-of-band mark
+    SIOCATMARK = 0x8905,
     /// Get Pty Number
     TIOCGPTN = 0x80045430,
     /// Lock/unlock Pty
     TIOCSPTLCK = 0x40045431,
+    /// Send a signal to the foreground process group of the pseudoterminal slave
+    TIOCSIG = 0x40045436,
+    /// Get whether the pseudoterminal master is in the packet mode
+    TIOCGPKT = 0x80045438,
+    /// Get the lock state of Pty
+    TIOCGPTLCK = 0x80045439,
     /// Safely open the slave
     TIOCGPTPEER = 0x40045441,
     /// Get tdx report using TDCALL
     TDXGETREPORT = 0xc4405401,
+    /// Get tdx quote using the `GetQuote` TDVMCALL
+    TDXGETQUOTE = 0x80105404,
+    /// Get the protocol version of the ALSA PCM interface
+    PCMPVERSION = 0x80044100,
+    /// Get the information of the PCM substream
+    PCMINFO = 0x81204101,
+    /// Set the hardware parameters of the PCM substream
+    PCMHWPARAMS = 0xc2604111,
+    /// Free the hardware parameters of the PCM substream
+    PCMHWFREE = 0x4112,
+    /// Prepare the PCM substream for playing
+    PCMPREPARE = 0x4140,
+    /// Start playing the PCM substream
+    PCMSTART = 0x4142,
+    /// Stop the PCM substream immediately, dropping the pending frames
+    PCMDROP = 0x4143,
+    /// Stop the PCM substream after the pending frames are played
+    PCMDRAIN = 0x4144,
+    /// Write interleaved frames to the PCM substream
+    PCMWRITEIFRAMES = 0x40184150,
+    /// Get whether the SCSI host adapter is emulated
+    SGEMULATEDHOST = 0x2203,
+    /// Get the version of the SCSI generic driver
+    SGGETVERSIONNUM = 0x2282,
+    /// Execute a SCSI command and wait for its completion
+    SGIO = 0x2285,
+    /// Re-read the partition table of the block device
+    BLKRRPART = 0x125f,
+    /// Get the logical sector size of the block device
+    BLKSSZGET = 0x1268,
+    /// Get the size in bytes of the block device
+    BLKGETSIZE64 = 0x80081272,
+    /// Get the version of the device-mapper ioctls
+    DMVERSION = 0xc138fd00,
+    /// Create a mapped device
+    DMDEVCREATE = 0xc138fd03,
+    /// Remove a mapped device
+    DMDEVREMOVE = 0xc138fd04,
+    /// Suspend a mapped device, or resume it and activate its inactive table
+    DMDEVSUSPEND = 0xc138fd06,
+    /// Get the status of a mapped device
+    DMDEVSTATUS = 0xc138fd07,
+    /// Load the inactive table of a mapped device
+    DMTABLELOAD = 0xc138fd09,
+    /// Clear the inactive table of a mapped device
+    DMTABLECLEAR = 0xc138fd0a,
+    /// Set the number of the words in the coverage trace
+    KCOVINITTRACE = 0x80086301,
+    /// Enable the coverage collection for the current thread
+    KCOVENABLE = 0x6364,
+    /// Disable the coverage collection for the current thread
+    KCOVDISABLE = 0x6365,
+    /// Receive a notification from the seccomp listener
+    SECCOMPNOTIFRECV = 0xc0502100,
+    /// Respond to a seccomp notification
+    SECCOMPNOTIFSEND = 0xc0182101,
+    /// Check whether a seccomp notification is still valid
+    SECCOMPNOTIFIDVALID = 0x40082102,
+    /// Install a file into the file table of the thread of a seccomp notification
+    SECCOMPNOTIFADDFD = 0x40182103,
 }
@@ -8,12 +8,17 @@ pub use creation_flags::CreationFlags;
 pub use dirent_visitor::DirentVisitor;
 pub use direntry_vec::DirEntryVecExt;
 pub use falloc_mode::FallocMode;
+pub use file_advice::FileAdvice;
 pub use file_creation_mask::FileCreationMask;
+pub use file_seals::FileSeals;
 pub use flock::{FlockItem, FlockList, FlockType};
 pub use fs::{FileSystem, FsFlags, SuperBlock};
 pub use inode::{Extension, Inode, InodeMode, InodeType, Metadata, MknodType, Permission};
 pub use ioctl::IoctlCmd;
-pub use page_cache::{CachePage, PageCache, PageCacheBackend};
+pub use page_cache::{
+    nr_dirty_pages, with_readahead_hint, AtomicReadaheadHint, CachePage, PageCache,
+    PageCacheBackend, ReadaheadHint,
+};
 pub use random_test::{generate_random_operation, new_fs_in_memory};
 pub use range_lock::{
     FileRange, RangeLockItem, RangeLockItemBuilder, RangeLockList, RangeLockType, OFFSET_MAX,
@@ -30,7 +35,9 @@ mod creation_flags;
 mod dirent_visitor;
 mod direntry_vec;
 mod falloc_mode;
+mod file_advice;
 mod file_creation_mask;
+mod file_seals;
 mod flock;
 mod fs;
 mod inode;
@@ -53,6 +60,10 @@ pub enum SeekFrom {
     Start(usize),
     End(isize),
     Current(isize),
+    /// Seeks to the next data at or after the offset.
+    Data(usize),
+    /// Seeks to the next hole at or after the offset.
+    Hole(usize),
 }
 
 /// Maximum bytes in a path
@@ -5,23 +5,39 @@
 use core::{
     iter,
     ops::Range,
-    sync::atomic::{AtomicU8, Ordering},
+    sync::atomic::{AtomicU8, AtomicUsize, Ordering},
 };
 
 use align_ext::AlignExt;
 use aster_block::bio::{BioStatus, BioWaiter};
 use aster_rights::Full;
+use atomic_integer_wrapper::define_atomic_version_of_integer_like_type;
 use lru::LruCache;
 use ostd::{
     impl_untyped_frame_meta_for,
     mm::{Frame, FrameAllocOptions, UFrame, VmIo},
+    task::Task,
 };
 
 use crate::{
+    fs::writeback,
     prelude::*,
-    vm::vmo::{get_page_idx_range, Pager, Vmo, VmoFlags, VmoOptions},
+    process::account_current_io,
+    sched::{PiMutex, PiMutexGuard},
+    vm::{
+        stat::{self, VmEvent},
+        vmo::{get_page_idx_range, Pager, Vmo, VmoFlags, VmoOptions},
+    },
 };
 
+/// The number of the dirty pages in all the page caches.
+static NR_DIRTY_PAGES: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the number of the dirty pages in all the page caches.
+pub fn nr_dirty_pages() -> usize {
+    NR_DIRTY_PAGES.load(Ordering::Relaxed)
+}
+
 pub struct PageCache {
     pages: Vmo<Full>,
     manager: Arc<PageCacheManager>,
@@ -140,6 +156,59 @@ impl Debug for PageCache {
     }
 }
 
+/// The hint on the access pattern of the reads, which adjusts the readahead.
+///
+/// The hint is advised for an opened file by `fadvise`, and is passed to the page cache during
+/// the reads of the file with [`with_readahead_hint`].
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, TryFromInt)]
+pub enum ReadaheadHint {
+    /// The readahead is performed for the sequential reads.
+    #[default]
+    Normal = 0,
+    /// No readahead is performed.
+    Random = 1,
+    /// The readahead is performed with a doubled maximum window.
+    Sequential = 2,
+}
+
+define_atomic_version_of_integer_like_type!(ReadaheadHint, try_from = true, {
+    #[derive(Debug)]
+    pub struct AtomicReadaheadHint(AtomicU8);
+});
+
+impl From<ReadaheadHint> for u8 {
+    fn from(value: ReadaheadHint) -> Self {
+        value as _
+    }
+}
+
+/// Performs the read operation, whose readahead follows the hint.
+pub fn with_readahead_hint<R>(hint: ReadaheadHint, op: impl FnOnce() -> Result<R>) -> Result<R> {
+    let Some(task) = Task::current() else {
+        return op();
+    };
+    let Some(thread_local) = task.as_thread_local() else {
+        return op();
+    };
+
+    let old_hint = thread_local.readahead_hint().replace(hint);
+    let res = op();
+    thread_local.readahead_hint().set(old_hint);
+    res
+}
+
+/// Returns the readahead hint of the read operation performed by the current thread.
+fn current_readahead_hint() -> ReadaheadHint {
+    let Some(task) = Task::current() else {
+        return ReadaheadHint::Normal;
+    };
+    task.as_thread_local()
+        .map_or(ReadaheadHint::Normal, |thread_local| {
+            thread_local.readahead_hint().get()
+        })
+}
+
 struct ReadaheadWindow {
     /// The window.
     window: Range<usize>,
@@ -216,6 +285,14 @@ impl ReadaheadState {
         self.max_size = size;
     }
 
+    /// Returns the maximum readahead window size for the reads with the hint.
+    fn max_window_size(&self, hint: ReadaheadHint) -> usize {
+        match hint {
+            ReadaheadHint::Sequential => self.max_size * 2,
+            ReadaheadHint::Normal | ReadaheadHint::Random => self.max_size,
+        }
+    }
+
     fn is_sequential(&self, idx: usize) -> bool {
         if let Some(prev) = self.prev_page {
             idx == prev || idx == prev + 1
@@ -249,7 +326,7 @@ impl ReadaheadState {
     /// Waits for the previous readahead.
     pub fn wait_for_prev_readahead(
         &mut self,
-        pages: &mut MutexGuard<LruCache<usize, CachePage>>,
+        pages: &mut PiMutexGuard<LruCache<usize, CachePage>>,
     ) -> Result<()> {
         if matches!(self.waiter.wait(), Some(BioStatus::Complete)) {
             let Some(window) = &self.ra_window else {
@@ -271,7 +348,11 @@ impl ReadaheadState {
     /// Determines whether a new readahead should be performed.
     /// We only consider readahead for sequential I/O now.
     /// There should be at most one in-progress readahead.
-    pub fn should_readahead(&self, idx: usize, max_page: usize) -> bool {
+    pub fn should_readahead(&self, idx: usize, max_page: usize, hint: ReadaheadHint) -> bool {
+        if hint == ReadaheadHint::Random {
+            return false;
+        }
+
         if self.request_number() == 0 && self.is_sequential(idx) {
             if let Some(cur_window) = &self.ra_window {
                 let trigger_readahead =
@@ -288,12 +369,13 @@ impl ReadaheadState {
     }
 
     /// Setup the new readahead window.
-    pub fn setup_window(&mut self, idx: usize, max_page: usize) {
+    pub fn setup_window(&mut self, idx: usize, max_page: usize, hint: ReadaheadHint) {
+        let max_size = self.max_window_size(hint);
         let new_window = if let Some(cur_window) = &self.ra_window {
-            cur_window.next(self.max_size, max_page)
+            cur_window.next(max_size, max_page)
         } else {
             let start_idx = idx + 1;
-            let init_size = Self::INIT_WINDOW_SIZE.min(self.max_size);
+            let init_size = Self::INIT_WINDOW_SIZE.min(max_size);
             let end_idx = (start_idx + init_size).min(max_page);
             ReadaheadWindow::new(start_idx..end_idx)
         };
@@ -304,7 +386,7 @@ impl ReadaheadState {
     /// Sends the relevant read request and sets the relevant page in the page cache to `Uninit`.
     pub fn conduct_readahead(
         &mut self,
-        pages: &mut MutexGuard<LruCache<usize, CachePage>>,
+        pages: &mut PiMutexGuard<LruCache<usize, CachePage>>,
         backend: Arc<dyn PageCacheBackend>,
     ) -> Result<()> {
         let Some(window) = &self.ra_window else {
@@ -314,6 +396,7 @@ impl ReadaheadState {
             let mut async_page = CachePage::alloc_uninit()?;
             let pg_waiter = backend.read_page_async(async_idx, &async_page)?;
             if pg_waiter.nreqs() > 0 {
+                account_current_io(|io_stats| io_stats.account_storage_read(PAGE_SIZE));
                 self.waiter.concat(pg_waiter);
             } else {
                 // Some backends (e.g. RamFS) do not issue requests, but fill the page directly.
@@ -331,17 +414,19 @@ impl ReadaheadState {
 }
 
 struct PageCacheManager {
-    pages: Mutex<LruCache<usize, CachePage>>,
+    // The locks inherit priorities, since threads of all priorities take them on every access
+    // to the file contents.
+    pages: PiMutex<LruCache<usize, CachePage>>,
     backend: Weak<dyn PageCacheBackend>,
-    ra_state: Mutex<ReadaheadState>,
+    ra_state: PiMutex<ReadaheadState>,
 }
 
 impl PageCacheManager {
     pub fn new(backend: Weak<dyn PageCacheBackend>) -> Self {
         Self {
-            pages: Mutex::new(LruCache::unbounded()),
+            pages: PiMutex::new(LruCache::unbounded()),
             backend,
-            ra_state: Mutex::new(ReadaheadState::new()),
+            ra_state: PiMutex::new(ReadaheadState::new()),
         }
     }
 
@@ -353,8 +438,20 @@ impl PageCacheManager {
     pub fn discard_range(&self, range: Range<usize>) {
         let page_idx_range = get_page_idx_range(&range);
         let mut pages = self.pages.lock();
+        let mut nr_cancelled_pages = 0;
         for idx in page_idx_range {
-            pages.pop(&idx);
+            if let Some(page) = pages.pop(&idx) {
+                if page.load_state() == PageState::Dirty {
+                    nr_cancelled_pages += 1;
+                }
+            }
+        }
+        drop(pages);
+
+        if nr_cancelled_pages > 0 {
+            account_current_io(|io_stats| {
+                io_stats.account_cancelled_write(nr_cancelled_pages * PAGE_SIZE)
+            });
         }
     }
 
@@ -365,11 +462,13 @@ impl PageCacheManager {
         let mut pages = self.pages.lock();
         let backend = self.backend();
         let backend_npages = backend.npages();
+        let mut nr_written = 0;
         for idx in page_idx_range.start..page_idx_range.end {
             if let Some(page) = pages.peek(&idx) {
                 if page.load_state() == PageState::Dirty && idx < backend_npages {
                     let waiter = backend.write_page_async(idx, page)?;
                     bio_waiter.concat(waiter);
+                    nr_written += 1;
                 }
             }
         }
@@ -378,6 +477,7 @@ impl PageCacheManager {
             // Do not allow partial failure
             return_errno!(Errno::EIO);
         }
+        stat::count_vm_events(VmEvent::NrWritten, nr_written);
 
         for (_, page) in pages
             .iter_mut()
@@ -417,7 +517,14 @@ impl PageCacheManager {
             // Conducts the sync read operation.
             let page = if idx < backend.npages() {
                 let mut page = CachePage::alloc_uninit()?;
-                backend.read_page(idx, &page)?;
+                let waiter = backend.read_page_async(idx, &page)?;
+                // Like the readahead, only the pages read from the storage are accounted.
+                if waiter.nreqs() > 0 {
+                    account_current_io(|io_stats| io_stats.account_storage_read(PAGE_SIZE));
+                }
+                if !matches!(waiter.wait(), Some(BioStatus::Complete)) {
+                    return_errno!(Errno::EIO);
+                }
                 page.store_state(PageState::UpToDate);
                 page
             } else {
@@ -427,8 +534,9 @@ impl PageCacheManager {
             pages.put(idx, page);
             frame
         };
-        if ra_state.should_readahead(idx, backend.npages()) {
-            ra_state.setup_window(idx, backend.npages());
+        let hint = current_readahead_hint();
+        if ra_state.should_readahead(idx, backend.npages(), hint) {
+            ra_state.setup_window(idx, backend.npages(), hint);
             ra_state.conduct_readahead(&mut pages, backend)?;
         }
         ra_state.set_prev_page(idx);
@@ -451,12 +559,22 @@ impl Pager for PageCacheManager {
 
     fn update_page(&self, idx: usize) -> Result<()> {
         let mut pages = self.pages.lock();
-        if let Some(page) = pages.get_mut(&idx) {
+        let is_newly_dirty = if let Some(page) = pages.get_mut(&idx) {
+            let is_newly_dirty = page.load_state() != PageState::Dirty;
             page.store_state(PageState::Dirty);
+            is_newly_dirty
         } else {
             warn!("The page {} is not in page cache", idx);
+            false
+        };
+        drop(pages);
+
+        // Like Linux, the bytes to be written back are accounted when the page becomes dirty.
+        if is_newly_dirty {
+            account_current_io(|io_stats| io_stats.account_storage_write(PAGE_SIZE));
         }
 
+        writeback::wake_if_too_dirty();
         Ok(())
     }
 
@@ -464,11 +582,15 @@ impl Pager for PageCacheManager {
         let page_result = self.pages.lock().pop(&idx);
         if let Some(page) = page_result {
             if let PageState::Dirty = page.load_state() {
-                let Some(backend) = self.backend.upgrade() else {
-                    return Ok(());
-                };
-                if idx < backend.npages() {
-                    backend.write_page(idx, &page)?;
+                let backend = self.backend.upgrade();
+                match backend {
+                    Some(backend) if idx < backend.npages() => {
+                        backend.write_page(idx, &page)?;
+                        stat::count_vm_event(VmEvent::NrWritten);
+                    }
+                    // The dirty page will never be written back, because it is beyond the end
+                    // of the backend (e.g., the file has been truncated) or the backend has gone.
+                    _ => account_current_io(|io_stats| io_stats.account_cancelled_write(PAGE_SIZE)),
                 }
             }
         }
@@ -476,6 +598,22 @@ impl Pager for PageCacheManager {
         Ok(())
     }
 
+    fn is_page_clean(&self, frame: &UFrame) -> bool {
+        if !self
+            .backend
+            .upgrade()
+            .is_some_and(|backend| backend.is_persistent())
+        {
+            return false;
+        }
+
+        let Some(meta) = (frame.dyn_meta() as &dyn Any).downcast_ref::<CachePageMeta>() else {
+            return false;
+        };
+        // The page is referenced only by the VMO and the LRU cache.
+        meta.state.load(Ordering::Relaxed) == PageState::UpToDate && frame.reference_count() == 2
+    }
+
     fn commit_overwrite(&self, idx: usize) -> Result<UFrame> {
         if let Some(page) = self.pages.lock().get(&idx) {
             return Ok(page.clone().into());
@@ -484,6 +622,15 @@ impl Pager for PageCacheManager {
         let page = CachePage::alloc_uninit()?;
         Ok(self.pages.lock().get_or_insert(idx, || page).clone().into())
     }
+
+    fn write_back(&self, idx_range: Range<usize>) -> Result<()> {
+        // The pages cannot be written back if the backend has gone.
+        if self.backend.strong_count() == 0 {
+            return Ok(());
+        }
+
+        self.evict_range(idx_range.start * PAGE_SIZE..idx_range.end * PAGE_SIZE)
+    }
 }
 
 /// A page in the page cache.
@@ -556,6 +703,8 @@ pub enum PageState {
 }
 
 /// A page state with atomic operations.
+///
+/// The dirty pages are counted in [`nr_dirty_pages`].
 #[derive(Debug)]
 pub struct AtomicPageState {
     state: AtomicU8,
@@ -563,13 +712,34 @@ pub struct AtomicPageState {
 
 impl AtomicPageState {
     pub fn new(state: PageState) -> Self {
+        if state == PageState::Dirty {
+            NR_DIRTY_PAGES.fetch_add(1, Ordering::Relaxed);
+            stat::count_vm_event(VmEvent::NrDirtied);
+        }
         Self {
             state: AtomicU8::new(state as _),
         }
     }
 
     pub fn load(&self, order: Ordering) -> PageState {
-        let val = self.state.load(order);
+        Self::from_u8(self.state.load(order))
+    }
+
+    pub fn store(&self, val: PageState, order: Ordering) {
+        let old_val = Self::from_u8(self.state.swap(val as u8, order));
+        match (old_val == PageState::Dirty, val == PageState::Dirty) {
+            (false, true) => {
+                NR_DIRTY_PAGES.fetch_add(1, Ordering::Relaxed);
+                stat::count_vm_event(VmEvent::NrDirtied);
+            }
+            (true, false) => {
+                NR_DIRTY_PAGES.fetch_sub(1, Ordering::Relaxed);
+            }
+            _ => (),
+        }
+    }
+
+    fn from_u8(val: u8) -> PageState {
         match val {
             0 => PageState::Uninit,
             1 => PageState::UpToDate,
@@ -577,9 +747,13 @@ impl AtomicPageState {
             _ => unreachable!(),
         }
     }
+}
 
-    pub fn store(&self, val: PageState, order: Ordering) {
-        self.state.store(val as u8, order);
+impl Drop for AtomicPageState {
+    fn drop(&mut self) {
+        if self.load(Ordering::Relaxed) == PageState::Dirty {
+            NR_DIRTY_PAGES.fetch_sub(1, Ordering::Relaxed);
+        }
     }
 }
 
@@ -591,6 +765,11 @@ pub trait PageCacheBackend: Sync + Send {
     fn write_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter>;
     /// Returns the number of pages in the backend.
     fn npages(&self) -> usize;
+    /// Returns whether the backend keeps the written pages, so that the clean pages can be
+    /// dropped and read again.
+    fn is_persistent(&self) -> bool {
+        true
+    }
 }
 
 impl dyn PageCacheBackend {
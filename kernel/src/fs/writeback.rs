@@ -0,0 +1,85 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The background writeback of the dirty pages.
+//!
+//! A delayed work item periodically writes back the dirty pages in the page caches, so that the
+//! changes of the files reach the devices even if the user space never calls `sync` or `fsync`.
+//! The work item is submitted earlier if the dirty pages take up too much memory.
+//!
+//! The dirty pages are written back by syncing all the mounted file systems. This keeps the
+//! order of the writes that each file system relies on to stay consistent (e.g., Ext2 writes
+//! back its bitmaps before its inode tables), at the cost of also writing back the metadata.
+
+use core::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use spin::Once;
+
+use crate::{
+    fs::{rootfs::root_mount, utils::nr_dirty_pages},
+    prelude::*,
+    thread::work_queue::{create_ordered_work_queue, DelayedWorkItem, WorkPriority, WorkQueue},
+};
+
+/// The interval between two periodic writebacks, which is the same as the default value of
+/// `/proc/sys/vm/dirty_writeback_centisecs` in Linux.
+const WRITEBACK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The percentage of the memory that the dirty pages can take up before they are written back
+/// early, which is the same as the default value of `/proc/sys/vm/dirty_background_ratio` in
+/// Linux.
+const DIRTY_BACKGROUND_RATIO: usize = 10;
+
+/// The work queue of the writebacks, which are processed one by one.
+static WRITEBACK_QUEUE: Once<Arc<WorkQueue>> = Once::new();
+
+static WRITEBACK_WORK: Once<Arc<DelayedWorkItem>> = Once::new();
+
+/// The number of the dirty pages that triggers an early writeback.
+///
+/// It is `usize::MAX` before the writeback work item is scheduled.
+static DIRTY_BACKGROUND_THRESHOLD: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Schedules the periodic writeback.
+///
+/// This function should be called after the file systems are mounted.
+pub(super) fn init() {
+    let work_queue = WRITEBACK_QUEUE.call_once(|| create_ordered_work_queue(WorkPriority::Normal));
+    let work = WRITEBACK_WORK.call_once(|| DelayedWorkItem::new(Box::new(writeback), work_queue));
+    work.schedule(WRITEBACK_INTERVAL);
+
+    let nr_total_pages = crate::vm::mem_total() / PAGE_SIZE;
+    DIRTY_BACKGROUND_THRESHOLD.store(
+        nr_total_pages * DIRTY_BACKGROUND_RATIO / 100,
+        Ordering::Relaxed,
+    );
+}
+
+/// Writes back the dirty pages early if they take up too much memory.
+///
+/// This function should be called after a page becomes dirty.
+pub(super) fn wake_if_too_dirty() {
+    if nr_dirty_pages() <= DIRTY_BACKGROUND_THRESHOLD.load(Ordering::Relaxed) {
+        return;
+    }
+
+    // The work item is not submitted again if it is still pending.
+    let work = WRITEBACK_WORK.get().unwrap();
+    WRITEBACK_QUEUE
+        .get()
+        .unwrap()
+        .enqueue(work.work_item().clone());
+}
+
+fn writeback() {
+    if nr_dirty_pages() != 0 {
+        if let Err(err) = root_mount().sync() {
+            warn!("failed to write back the dirty pages: {:?}", err);
+        }
+    }
+
+    // This also postpones the periodic writeback after an early one.
+    WRITEBACK_WORK.get().unwrap().schedule(WRITEBACK_INTERVAL);
+}
@@ -6,6 +6,7 @@ use crate::{
 };
 
 pub mod semaphore;
+pub mod shm;
 
 #[expect(non_camel_case_types)]
 pub type key_t = i32;
@@ -99,4 +100,5 @@ impl IpcPermission {
 
 pub(super) fn init() {
     semaphore::init();
+    shm::init();
 }
@@ -13,8 +13,8 @@ use super::sem_set::{SemSetInner, SEMVMX};
 use crate::{
     ipc::{key_t, semaphore::system_v::sem_set::sem_sets, IpcFlags},
     prelude::*,
-    process::Pid,
-    time::{clocks::JIFFIES_TIMER_MANAGER, timer::Timeout},
+    process::{signal::Pause, Pid},
+    time::{clocks::JIFFIES_TIMER_MANAGER, timer::Timeout, wait::TimeoutExt},
 };
 
 #[derive(Clone, Copy, Debug, Pod)]
@@ -199,7 +199,9 @@ pub fn sem_op(
     drop(inner);
     drop(local_sem_sets);
 
-    waiter.wait();
+    // The wait is interrupted if the current thread is signalled (including being killed), in
+    // which case the pending operation is cancelled below.
+    let wait_res = waiter.pause_timeout(&TimeoutExt::Never);
     match status.load(Ordering::Relaxed) {
         Status::Normal => Ok(()),
         Status::Removed => Err(Error::new(Errno::EIDRM)),
@@ -216,6 +218,7 @@ pub fn sem_op(
             };
             pending_ops.retain(|op| op.pid != pid);
 
+            wait_res?;
             Err(Error::new(Errno::EAGAIN))
         }
     }
@@ -0,0 +1,476 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! System V shared memory.
+//!
+//! A shared memory segment is backed by a VMO, which is mapped as a shared mapping into the
+//! processes that attach to the segment. The number of the attaches is the number of the mappings
+//! that refer to the VMO, so the mappings that are inherited by `fork` or dropped on `exit` and
+//! `execve` are counted correctly, as in Linux.
+//!
+//! POSIX shared memory (i.e., `shm_open`) does not need any kernel support other than the tmpfs
+//! mounted at `/dev/shm`, see [`crate::device`].
+
+use align_ext::AlignExt;
+use aster_rights::{ReadOp, Rights};
+use id_alloc::IdAlloc;
+use spin::Once;
+
+use super::{key_t, IpcPermission};
+use crate::{
+    prelude::*,
+    process::{credentials::capabilities::CapSet, Credentials, Gid, Pid, Uid},
+    time::clocks::RealTimeCoarseClock,
+    vm::{
+        hugetlb::HugePagePool,
+        vmo::{Vmo, VmoOptions},
+    },
+};
+
+// The following constant values are derived from the default values in Linux.
+
+/// Minimum size of a shared memory segment in bytes.
+pub const SHMMIN: usize = 1;
+/// Maximum size of a shared memory segment in bytes.
+pub const SHMMAX: usize = usize::MAX - (1 << 24);
+/// Maximum number of pages of all shared memory segments.
+pub const SHMALL: usize = usize::MAX - (1 << 24);
+/// Maximum number of shared memory segments.
+pub const SHMMNI: usize = 4096;
+
+/// The private key, with which `shmget` always creates a new segment.
+pub const IPC_PRIVATE: key_t = 0;
+
+/// The flag of `shmget` to create the segment with huge pages.
+pub const SHM_HUGETLB: u32 = 0o4000;
+
+bitflags! {
+    /// The flags of `shmat`.
+    pub struct ShmAtFlags: u32 {
+        /// Attach the segment for read-only access.
+        const SHM_RDONLY = 0o10000;
+        /// Round the attach address down to a multiple of `SHMLBA`.
+        const SHM_RND = 0o20000;
+        /// Take over the existing mappings in the attach range.
+        const SHM_REMAP = 0o40000;
+        /// Attach the segment for execute access.
+        const SHM_EXEC = 0o100000;
+    }
+}
+
+bitflags! {
+    /// The flags in the permission mode of a shared memory segment that are reported by
+    /// `shmctl(IPC_STAT)`.
+    pub struct ShmModeFlags: u32 {
+        /// The segment will be destroyed when the last process detaches it.
+        const SHM_DEST = 0o1000;
+        /// The segment is locked in memory.
+        const SHM_LOCKED = 0o2000;
+    }
+}
+
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+#[expect(non_camel_case_types)]
+pub enum ShmControlCmd {
+    IPC_RMID = 0,
+    IPC_SET = 1,
+    IPC_STAT = 2,
+    IPC_INFO = 3,
+
+    SHM_LOCK = 11,
+    SHM_UNLOCK = 12,
+    SHM_STAT = 13,
+    SHM_INFO = 14,
+    SHM_STAT_ANY = 15,
+}
+
+/// A System V shared memory segment.
+#[derive(Debug)]
+pub struct ShmSegment {
+    id: i32,
+    /// The size requested by `shmget`, which may not be page-aligned.
+    size: usize,
+    vmo: Vmo<Rights>,
+    /// The PID of the creator.
+    cpid: Pid,
+    inner: Mutex<ShmSegmentInner>,
+}
+
+#[derive(Debug)]
+struct ShmSegmentInner {
+    permission: IpcPermission,
+    flags: ShmModeFlags,
+    /// Last attach time
+    atime: u64,
+    /// Last detach time
+    dtime: u64,
+    /// Creation time or last change time via `shmctl`
+    ctime: u64,
+    /// The PID of the last process that attaches or detaches the segment.
+    lpid: Pid,
+}
+
+/// The status of a shared memory segment, as reported by `shmctl(IPC_STAT)`.
+#[derive(Debug, Clone, Copy)]
+pub struct ShmStat {
+    pub key: key_t,
+    pub uid: Uid,
+    pub gid: Gid,
+    pub cuid: Uid,
+    pub cgid: Gid,
+    pub mode: u32,
+    pub size: usize,
+    pub atime: u64,
+    pub dtime: u64,
+    pub ctime: u64,
+    pub cpid: Pid,
+    pub lpid: Pid,
+    pub nattch: usize,
+}
+
+impl ShmSegment {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    /// Returns the size requested by `shmget` in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the number of the mappings that attach to the segment.
+    pub fn nattch(&self) -> usize {
+        // The segment holds a capability of the VMO itself.
+        self.vmo.num_handles() - 1
+    }
+
+    /// Returns whether the segment will be destroyed when the last process detaches it.
+    pub fn is_destroyed(&self) -> bool {
+        self.inner.lock().flags.contains(ShmModeFlags::SHM_DEST)
+    }
+
+    /// Returns whether the VMO is the one that backs the segment.
+    pub fn is_backed_by(&self, vmo: &Vmo) -> bool {
+        self.vmo == *vmo
+    }
+
+    /// Returns a capability of the VMO for attaching the segment.
+    ///
+    /// The caller must check the permission with [`Self::check_perm`] first.
+    pub fn dup_vmo(&self) -> Result<Vmo<Rights>> {
+        self.vmo.dup()
+    }
+
+    /// Records that the segment has been attached by the process.
+    pub fn set_attached(&self, pid: Pid) {
+        let mut inner = self.inner.lock();
+        inner.atime = now();
+        inner.lpid = pid;
+    }
+
+    /// Records that the segment has been detached by the process.
+    pub fn set_detached(&self, pid: Pid) {
+        let mut inner = self.inner.lock();
+        inner.dtime = now();
+        inner.lpid = pid;
+    }
+
+    /// Checks whether the accesses in `requested` (i.e., `0o4` for reading and `0o2` for
+    /// writing) are allowed.
+    pub fn check_perm(&self, credentials: &Credentials<ReadOp>, requested: u16) -> Result<()> {
+        if requested == 0 {
+            return Ok(());
+        }
+
+        let inner = self.inner.lock();
+        let permission = &inner.permission;
+        let euid = credentials.euid();
+        let egid = credentials.egid();
+        let granted = if euid == permission.uid || euid == permission.cuid {
+            permission.mode >> 6
+        } else if egid == permission.gid || egid == permission.cguid {
+            permission.mode >> 3
+        } else {
+            permission.mode
+        } & 0o7;
+
+        if requested & !granted != 0 && !credentials.has_capability(CapSet::IPC_OWNER) {
+            return_errno_with_message!(
+                Errno::EACCES,
+                "the shared memory segment cannot be accessed"
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks whether the segment can be changed (e.g., removed) by the credentials.
+    fn check_owner(&self, credentials: &Credentials<ReadOp>) -> Result<()> {
+        let inner = self.inner.lock();
+        let euid = credentials.euid();
+        if euid != inner.permission.uid
+            && euid != inner.permission.cuid
+            && !credentials.has_capability(CapSet::SYS_ADMIN)
+        {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "the current process does not own the shared memory segment"
+            );
+        }
+        Ok(())
+    }
+
+    /// Sets the owner and the permission mode, as in `shmctl(IPC_SET)`.
+    pub fn set_perm(
+        &self,
+        uid: Uid,
+        gid: Gid,
+        mode: u16,
+        credentials: &Credentials<ReadOp>,
+    ) -> Result<()> {
+        self.check_owner(credentials)?;
+
+        let mut inner = self.inner.lock();
+        inner.permission.uid = uid;
+        inner.permission.gid = gid;
+        inner.permission.mode = mode & 0o777;
+        inner.ctime = now();
+        Ok(())
+    }
+
+    /// Locks or unlocks the segment in memory, as in `shmctl(SHM_LOCK)` and `shmctl(SHM_UNLOCK)`.
+    ///
+    /// The pages of the segment are never swapped out, so this only changes the reported mode.
+    pub fn set_locked(&self, locked: bool, credentials: &Credentials<ReadOp>) -> Result<()> {
+        if !credentials.has_capability(CapSet::IPC_LOCK) {
+            self.check_owner(credentials)?;
+        }
+
+        self.inner
+            .lock()
+            .flags
+            .set(ShmModeFlags::SHM_LOCKED, locked);
+        Ok(())
+    }
+
+    pub fn stat(&self) -> ShmStat {
+        let inner = self.inner.lock();
+        let permission = &inner.permission;
+        ShmStat {
+            key: permission.key,
+            uid: permission.uid,
+            gid: permission.gid,
+            cuid: permission.cuid,
+            cgid: permission.cguid,
+            mode: permission.mode as u32 | inner.flags.bits(),
+            size: self.size,
+            atime: inner.atime,
+            dtime: inner.dtime,
+            ctime: inner.ctime,
+            cpid: self.cpid,
+            lpid: inner.lpid,
+            nattch: self.nattch(),
+        }
+    }
+
+    fn num_pages(&self) -> usize {
+        self.vmo.size() / PAGE_SIZE
+    }
+}
+
+/// Finds the segment with the key, or creates one if it does not exist and `create` is true.
+///
+/// The segment is created with the huge pages from `huge_pool` if it is given.
+///
+/// This function returns the segment and whether it is newly created.
+pub fn get_or_create_shm(
+    key: key_t,
+    size: usize,
+    mode: u16,
+    create: bool,
+    huge_pool: Option<&'static HugePagePool>,
+    pid: Pid,
+    credentials: &Credentials<ReadOp>,
+) -> Result<(Arc<ShmSegment>, bool)> {
+    let mut table = shm_table();
+
+    if key != IPC_PRIVATE {
+        if let Some(segment) = table
+            .segments
+            .values()
+            .find(|segment| segment.inner.lock().permission.key == key)
+        {
+            return Ok((segment.clone(), false));
+        }
+        if !create {
+            return_errno_with_message!(
+                Errno::ENOENT,
+                "no shared memory segment exists for the key"
+            );
+        }
+    }
+
+    if !(SHMMIN..=SHMMAX).contains(&size) {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the size of the shared memory segment is invalid"
+        );
+    }
+    let vmo_size = size.align_up(huge_pool.map_or(PAGE_SIZE, HugePagePool::page_size));
+    let num_pages = vmo_size / PAGE_SIZE;
+    if table.total_pages + num_pages > SHMALL {
+        return_errno_with_message!(Errno::ENOSPC, "the shared memory is used up");
+    }
+
+    let id = table.id_alloc.alloc().ok_or(Error::with_message(
+        Errno::ENOSPC,
+        "the number of shared memory segments reaches the limit",
+    ))? as i32;
+    let vmo = match huge_pool {
+        Some(pool) => pool.alloc_vmo(vmo_size, None),
+        None => VmoOptions::<Rights>::new(vmo_size).alloc(),
+    };
+    let vmo = match vmo {
+        Ok(vmo) => vmo,
+        Err(err) => {
+            table.id_alloc.free(id as usize);
+            return Err(err);
+        }
+    };
+
+    let uid = credentials.euid();
+    let gid = credentials.egid();
+    let segment = Arc::new(ShmSegment {
+        id,
+        size,
+        vmo,
+        cpid: pid,
+        inner: Mutex::new(ShmSegmentInner {
+            permission: IpcPermission {
+                key,
+                uid,
+                gid,
+                cuid: uid,
+                cguid: gid,
+                mode: mode & 0o777,
+            },
+            flags: ShmModeFlags::empty(),
+            atime: 0,
+            dtime: 0,
+            ctime: now(),
+            lpid: 0,
+        }),
+    });
+    table.segments.insert(id, segment.clone());
+    table.total_pages += num_pages;
+
+    Ok((segment, true))
+}
+
+/// Returns the segment with the ID.
+pub fn get_shm(id: i32) -> Result<Arc<ShmSegment>> {
+    shm_table()
+        .segments
+        .get(&id)
+        .cloned()
+        .ok_or(Error::with_message(
+            Errno::EINVAL,
+            "the shared memory segment does not exist",
+        ))
+}
+
+/// Finds the segment that is backed by the VMO.
+pub fn find_shm_by_vmo(vmo: &Vmo) -> Option<Arc<ShmSegment>> {
+    shm_table()
+        .segments
+        .values()
+        .find(|segment| segment.is_backed_by(vmo))
+        .cloned()
+}
+
+/// Marks the segment to be destroyed, as in `shmctl(IPC_RMID)`.
+///
+/// The segment is destroyed immediately if no process attaches to it. Otherwise, it is destroyed
+/// when the last process detaches it. In the meantime, the segment can no longer be found by its
+/// key.
+pub fn remove_shm(id: i32, credentials: &Credentials<ReadOp>) -> Result<()> {
+    let segment = get_shm(id)?;
+    segment.check_owner(credentials)?;
+
+    {
+        let mut inner = segment.inner.lock();
+        inner.flags.insert(ShmModeFlags::SHM_DEST);
+        inner.permission.key = IPC_PRIVATE;
+    }
+
+    drop(segment);
+    collect_destroyed_shm();
+    Ok(())
+}
+
+/// Destroys the segments that have been marked to be destroyed and have no attaches.
+///
+/// The segments cannot be destroyed when they are detached because the mappings are unaware of
+/// the segments, so this function should be called after any mappings may be removed, e.g.,
+/// when a process detaches a segment or exits.
+pub fn collect_destroyed_shm() {
+    let mut table = shm_table();
+
+    let destroyed_ids: Vec<_> = table
+        .segments
+        .values()
+        .filter(|segment| segment.is_destroyed() && segment.nattch() == 0)
+        .map(|segment| segment.id)
+        .collect();
+    for id in destroyed_ids {
+        let segment = table.segments.remove(&id).unwrap();
+        table.total_pages -= segment.num_pages();
+        table.id_alloc.free(id as usize);
+    }
+}
+
+/// The usage of the shared memory, as reported by `shmctl(SHM_INFO)`.
+#[derive(Debug, Clone, Copy)]
+pub struct ShmUsage {
+    /// The number of the existing segments
+    pub used_ids: usize,
+    /// The total number of the pages of the segments
+    pub total_pages: usize,
+    /// The largest ID of the existing segments
+    pub max_id: i32,
+}
+
+pub fn shm_usage() -> ShmUsage {
+    let table = shm_table();
+    ShmUsage {
+        used_ids: table.segments.len(),
+        total_pages: table.total_pages,
+        max_id: table.segments.keys().last().copied().unwrap_or(0),
+    }
+}
+
+struct ShmTable {
+    segments: BTreeMap<i32, Arc<ShmSegment>>,
+    id_alloc: IdAlloc,
+    /// The total number of the pages of the segments
+    total_pages: usize,
+}
+
+static SHM_TABLE: Once<Mutex<ShmTable>> = Once::new();
+
+fn shm_table() -> MutexGuard<'static, ShmTable> {
+    SHM_TABLE.get().unwrap().lock()
+}
+
+fn now() -> u64 {
+    RealTimeCoarseClock::get().read_time().as_secs()
+}
+
+pub(super) fn init() {
+    SHM_TABLE.call_once(|| {
+        Mutex::new(ShmTable {
+            segments: BTreeMap::new(),
+            id_alloc: IdAlloc::with_capacity(SHMMNI),
+            total_pages: 0,
+        })
+    });
+}
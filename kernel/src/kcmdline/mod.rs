@@ -0,0 +1,228 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The module to parse kernel command-line arguments.
+//!
+//! The format of the Asterinas command line string conforms
+//! to the Linux kernel command line rules:
+//!
+//! <https://www.kernel.org/doc/html/v6.4/admin-guide/kernel-parameters.html>
+//!
+//! The arguments in the form of `<module>.<name>[=<value>]` are kernel parameters, which are
+//! declared by the subsystems with [`kernel_param!`] and read with [`KernelParam::get`]. A few
+//! parameters have no module, e.g., `console=`, which are recognized if they are declared. The
+//! other arguments, except `init=`, are passed to the init process as in Linux.
+//!
+//! [`kernel_param!`]: crate::kernel_param
+
+mod param;
+
+use ostd::boot::boot_info;
+use spin::Once;
+
+pub use self::param::{KernelParam, ParamValue};
+#[doc(hidden)]
+pub use self::param::{ParamDesc, ParamEntry};
+use crate::prelude::*;
+
+#[derive(PartialEq, Debug)]
+struct InitprocArgs {
+    path: Option<String>,
+    argv: Vec<CString>,
+    envp: Vec<CString>,
+}
+
+/// The struct to store the parsed kernel command-line arguments.
+#[derive(Debug)]
+pub struct KCmdlineArg {
+    initproc: InitprocArgs,
+    /// The values of the kernel parameters, where the last one takes effect if a parameter is
+    /// given more than once
+    params: BTreeMap<String, Option<String>>,
+}
+
+// Define get APIs.
+impl KCmdlineArg {
+    /// Gets the path of the initprocess.
+    pub fn get_initproc_path(&self) -> Option<&str> {
+        self.initproc.path.as_deref()
+    }
+    /// Gets the argument vector(argv) of the initprocess.
+    pub fn get_initproc_argv(&self) -> &Vec<CString> {
+        &self.initproc.argv
+    }
+    /// Gets the environment vector(envp) of the initprocess.
+    pub fn get_initproc_envp(&self) -> &Vec<CString> {
+        &self.initproc.envp
+    }
+    /// Gets the value of a kernel parameter.
+    ///
+    /// The outer `None` means that the parameter is not given, while the inner `None` means that
+    /// the parameter is given without a value.
+    pub fn get_param(&self, name: &str) -> Option<Option<&str>> {
+        self.params.get(name).map(Option::as_deref)
+    }
+}
+
+crate::kernel_param! {
+    /// Prints the documentation of all the kernel parameters at boot time.
+    static DUMP_DOCS: bool = ("kcmdline.dump_docs", false);
+}
+
+/// Returns the parsed kernel command-line arguments.
+pub fn kcmdline() -> &'static KCmdlineArg {
+    static KCMDLINE: Once<KCmdlineArg> = Once::new();
+
+    KCMDLINE.call_once(|| KCmdlineArg::from(boot_info().kernel_cmdline.as_str()))
+}
+
+/// Checks the kernel parameters in the command line.
+///
+/// The parameters that are not declared or have invalid values are reported, and the default
+/// values are used for them.
+pub(crate) fn init() {
+    for (name, value) in kcmdline().params.iter() {
+        let Some(param) = param::find_param(name) else {
+            warn!("unknown kernel parameter `{}`, ignored", name);
+            continue;
+        };
+        if !param.is_valid(value.as_deref()) {
+            warn!(
+                "invalid value of kernel parameter `{}`, the default `{}` is used",
+                name,
+                param.default_value()
+            );
+        }
+    }
+
+    if DUMP_DOCS.get() {
+        println!("{}", param::generate_docs());
+    }
+}
+
+// Splits the command line string by spaces but preserve
+// ones that are protected by double quotes(`"`).
+fn split_arg(input: &str) -> impl Iterator<Item = &str> {
+    let mut inside_quotes = false;
+
+    input.split(move |c: char| {
+        if c == '"' {
+            inside_quotes = !inside_quotes;
+        }
+
+        !inside_quotes && c.is_whitespace()
+    })
+}
+
+// Removes the double quotes around a value, e.g., `"a b"`.
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+// Define the way to parse a string to `KCmdlineArg`.
+impl From<&str> for KCmdlineArg {
+    fn from(cmdline: &str) -> Self {
+        // What we construct.
+        let mut result: KCmdlineArg = KCmdlineArg {
+            initproc: InitprocArgs {
+                path: None,
+                argv: Vec::new(),
+                envp: Vec::new(),
+            },
+            params: BTreeMap::new(),
+        };
+
+        // Every thing after the "--" mark is the initproc arguments.
+        let mut kcmdline_end = false;
+
+        // The main parse loop. The processing steps are arranged (not very strictly)
+        // by the analysis over the Backus–Naur form syntax tree.
+        for arg in split_arg(cmdline).filter(|arg| !arg.is_empty()) {
+            // Cmdline => KernelArg "--" InitArg
+            // KernelArg => Arg "\s+" KernelArg | %empty
+            // InitArg => Arg "\s+" InitArg | %empty
+            if kcmdline_end {
+                if result.initproc.path.is_none() {
+                    panic!("Initproc arguments provided but no initproc path specified!");
+                }
+                result.initproc.argv.push(CString::new(arg).unwrap());
+                continue;
+            }
+            if arg == "--" {
+                kcmdline_end = true;
+                continue;
+            }
+            // Arg => Entry | Entry "=" Value
+            let (entry, value) = match arg.split_once('=') {
+                Some((entry, value)) => (entry, Some(unquote(value))),
+                None => (arg, None),
+            };
+            // Entry => Module "." ParamName | ParamName | KernelOptionName
+            if entry.contains('.') || param::find_param(entry).is_some() {
+                result
+                    .params
+                    .insert(entry.to_string(), value.map(str::to_string));
+                continue;
+            }
+            // KernelOptionName => /*literal string alternatives*/ | /*init environment*/
+            if let Some(value) = value {
+                // The option has a value.
+                match entry {
+                    "init" => {
+                        if result.initproc.path.is_some() {
+                            panic!("Initproc assigned twice in the command line!");
+                        }
+                        result.initproc.path = Some(value.to_string());
+                    }
+                    _ => {
+                        // If the option is not recognized, it is passed to the initproc.
+                        // Pattern 'option=value' is treated as the init environment.
+                        let envp_entry = CString::new(entry.to_string() + "=" + value).unwrap();
+                        result.initproc.envp.push(envp_entry);
+                    }
+                }
+            } else {
+                // There is no value, the entry is only a option.
+
+                // If the option is not recognized, it is passed to the initproc.
+                // Pattern 'option' without value is treated as the init argument.
+                let argv_entry = CString::new(entry.to_string()).unwrap();
+                result.initproc.argv.push(argv_entry);
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn parse_cmdline() {
+        let karg = KCmdlineArg::from(
+            "HOME=/ console=ttyS0 syscall.audit klog.console_level=3 klog.console_level=5 \
+             dm-mod.create=\"a,,,ro,0 8 linear /dev/vda 0\" init=/bin/sh -- -l",
+        );
+        assert_eq!(karg.get_initproc_path(), Some("/bin/sh"));
+        assert_eq!(karg.get_initproc_argv(), &vec![CString::new("-l").unwrap()]);
+        assert_eq!(
+            karg.get_initproc_envp(),
+            &vec![CString::new("HOME=/").unwrap()]
+        );
+
+        assert_eq!(karg.get_param("console"), Some(Some("ttyS0")));
+        assert_eq!(karg.get_param("syscall.audit"), Some(None));
+        assert_eq!(karg.get_param("klog.console_level"), Some(Some("5")));
+        assert_eq!(
+            karg.get_param("dm-mod.create"),
+            Some(Some("a,,,ro,0 8 linear /dev/vda 0"))
+        );
+        assert_eq!(karg.get_param("profiler.freq"), None);
+    }
+}
@@ -0,0 +1,288 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The registry of the kernel parameters.
+//!
+//! A subsystem declares a parameter with its type, its default value, and its description, e.g.,
+//!
+//! ```ignore
+//! crate::kernel_param! {
+//!     /// The frequency of the samples in Hz, or 0 to disable the profiler.
+//!     static FREQ: u64 = ("profiler.freq", 0);
+//! }
+//! ```
+//!
+//! and reads the value in the command line with [`KernelParam::get`]. All the declared
+//! parameters are collected at link time, so the unknown ones in the command line can be
+//! reported, and the documentation of the parameters is generated from the same declarations.
+
+use core::fmt::{Display, Write};
+
+use super::kcmdline;
+use crate::prelude::*;
+
+/// Declares a kernel parameter.
+///
+/// The doc comments are used as the description of the parameter in the generated
+/// documentation.
+#[macro_export]
+macro_rules! kernel_param {
+    (
+        $(#[doc = $doc:literal])*
+        $vis:vis static $ident:ident: $ty:ty = ($name:literal, $default:expr);
+    ) => {
+        $(#[doc = $doc])*
+        $vis static $ident: $crate::kcmdline::KernelParam<$ty> =
+            $crate::kcmdline::KernelParam::new($name, $default, concat!($($doc, "\n"),*));
+
+        ::inventory::submit! {
+            $crate::kcmdline::ParamEntry(&$ident)
+        }
+    };
+}
+
+/// A kernel parameter of the type `T`.
+pub struct KernelParam<T> {
+    name: &'static str,
+    default: T,
+    description: &'static str,
+}
+
+impl<T: ParamValue> KernelParam<T> {
+    #[doc(hidden)]
+    pub const fn new(name: &'static str, default: T, description: &'static str) -> Self {
+        Self {
+            name,
+            default,
+            description,
+        }
+    }
+
+    /// Returns the value in the command line, or the default value if the parameter is not
+    /// given or the value is invalid.
+    pub fn get(&self) -> T {
+        kcmdline()
+            .get_param(self.name)
+            .and_then(T::parse)
+            .unwrap_or(self.default)
+    }
+}
+
+/// The type of the values of the kernel parameters.
+pub trait ParamValue: Copy + Display + Sync + 'static {
+    /// The name of the type in the documentation.
+    const TYPE_NAME: &'static str;
+
+    /// Parses the value, which is `None` if the parameter is given without a value.
+    fn parse(value: Option<&'static str>) -> Option<Self>;
+}
+
+impl ParamValue for bool {
+    const TYPE_NAME: &'static str = "bool";
+
+    fn parse(value: Option<&'static str>) -> Option<Self> {
+        // A boolean parameter without a value is enabled, e.g., `syscall.audit`.
+        match value {
+            None | Some("1" | "y" | "Y" | "on" | "true") => Some(true),
+            Some("0" | "n" | "N" | "off" | "false") => Some(false),
+            Some(_) => None,
+        }
+    }
+}
+
+macro_rules! impl_param_value_for_int {
+    ($($ty:ty),*) => {
+        $(
+            impl ParamValue for $ty {
+                const TYPE_NAME: &'static str = stringify!($ty);
+
+                fn parse(value: Option<&'static str>) -> Option<Self> {
+                    value?.parse().ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_param_value_for_int!(u8, u32, u64, usize);
+
+impl ParamValue for &'static str {
+    const TYPE_NAME: &'static str = "string";
+
+    fn parse(value: Option<&'static str>) -> Option<Self> {
+        value
+    }
+}
+
+/// The type-erased interface of [`KernelParam`].
+#[doc(hidden)]
+pub trait ParamDesc: Sync {
+    fn name(&self) -> &'static str;
+    fn type_name(&self) -> &'static str;
+    fn default_value(&self) -> String;
+    fn description(&self) -> &'static str;
+    fn is_valid(&self, value: Option<&'static str>) -> bool;
+}
+
+impl<T: ParamValue> ParamDesc for KernelParam<T> {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn type_name(&self) -> &'static str {
+        T::TYPE_NAME
+    }
+
+    fn default_value(&self) -> String {
+        self.default.to_string()
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn is_valid(&self, value: Option<&'static str>) -> bool {
+        T::parse(value).is_some()
+    }
+}
+
+/// An entry in the registry of the kernel parameters.
+#[doc(hidden)]
+pub struct ParamEntry(pub &'static dyn ParamDesc);
+
+inventory::collect!(ParamEntry);
+
+/// Returns all the declared parameters, sorted by their names.
+fn all_params() -> Vec<&'static dyn ParamDesc> {
+    let mut params: Vec<_> = inventory::iter::<ParamEntry>
+        .into_iter()
+        .map(|entry| entry.0)
+        .collect();
+    params.sort_by_key(|param| param.name());
+    params
+}
+
+/// Finds the declared parameter with the name.
+pub(super) fn find_param(name: &str) -> Option<&'static dyn ParamDesc> {
+    inventory::iter::<ParamEntry>
+        .into_iter()
+        .map(|entry| entry.0)
+        .find(|param| param.name() == name)
+}
+
+/// Generates the documentation of all the declared parameters in Markdown.
+pub(super) fn generate_docs() -> String {
+    let mut docs = String::new();
+    for param in all_params() {
+        if !docs.is_empty() {
+            docs.push('\n');
+        }
+        writeln!(docs, "### `{}`\n", param.name()).unwrap();
+        writeln!(docs, "- Type: `{}`", param.type_name()).unwrap();
+        match param.default_value().as_str() {
+            "" => writeln!(docs, "- Default: empty\n").unwrap(),
+            default => writeln!(docs, "- Default: `{}`\n", default).unwrap(),
+        }
+        // Each line of the doc comments starts with a space.
+        for line in param.description().lines() {
+            writeln!(docs, "{}", line.strip_prefix(' ').unwrap_or(line)).unwrap();
+        }
+    }
+    docs
+}
+
+// OSTD parses its parameters by itself, since it does not depend on the kernel. They are
+// declared here so that they are known and documented.
+
+crate::kernel_param! {
+    /// The maximum level of the logs printed by the `log` crate, which is one of `off`, `error`,
+    /// `warn`, `info`, `debug`, and `trace`.
+    ///
+    /// This parameter is parsed by OSTD.
+    static OSTD_LOG_LEVEL: &'static str = ("ostd.log_level", "off");
+}
+
+crate::kernel_param! {
+    /// Dedicates a serial port to the GDB stub, which is one of `ttyS1`, `ttyS2`, and `ttyS3`.
+    /// The stub is disabled if it is empty.
+    ///
+    /// This parameter is parsed by OSTD.
+    static OSTD_GDBSTUB: &'static str = ("ostd.gdbstub", "");
+}
+
+crate::kernel_param! {
+    /// Stops the kernel at boot and waits for GDB to connect to the GDB stub.
+    ///
+    /// This parameter is parsed by OSTD.
+    static OSTD_GDBWAIT: bool = ("ostd.gdbwait", false);
+}
+
+crate::kernel_param! {
+    /// Enables the kernel page-table isolation if it is `on`.
+    ///
+    /// This parameter is parsed by OSTD.
+    static OSTD_KPTI: &'static str = ("ostd.kpti", "off");
+}
+
+// The consoles are selected before the kernel is initialized, so aster-console parses the
+// parameters by itself.
+
+crate::kernel_param! {
+    /// Selects a console in the form of `<name>[,loglevel:<level>]`, where the name is `hvc0`
+    /// (the virtio console), `tty0` (the framebuffer console), or `ttyS0` (the serial port).
+    ///
+    /// The parameter can be given more than once. The kernel messages are printed on all the
+    /// selected consoles, and `/dev/console` is the last one. A console with `loglevel:<level>`
+    /// only prints the messages below the level, instead of `klog.console_level`. If none is
+    /// given, all the consoles except the serial port are used.
+    ///
+    /// This parameter is parsed by aster-console.
+    static CONSOLE: &'static str = ("console", "");
+}
+
+crate::kernel_param! {
+    /// Prints the kernel messages on the serial port until a selected console is available.
+    ///
+    /// This parameter is parsed by aster-console.
+    static EARLYCON: bool = ("earlycon", false);
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn parse_values() {
+        assert_eq!(bool::parse(None), Some(true));
+        assert_eq!(bool::parse(Some("off")), Some(false));
+        assert_eq!(bool::parse(Some("maybe")), None);
+        assert_eq!(u8::parse(Some("7")), Some(7));
+        assert_eq!(u8::parse(Some("256")), None);
+        assert_eq!(u64::parse(None), None);
+        assert_eq!(<&str as ParamValue>::parse(Some("a,b")), Some("a,b"));
+    }
+
+    #[ktest]
+    fn unique_names() {
+        let params = all_params();
+        for pair in params.windows(2) {
+            assert_ne!(pair[0].name(), pair[1].name());
+        }
+    }
+
+    // The parameters of the profiler are only declared on x86-64.
+    #[cfg(target_arch = "x86_64")]
+    #[ktest]
+    fn docs_in_sync() {
+        const DOCS: &str = include_str!("../../../docs/src/kernel/kernel-parameters.md");
+
+        let generated = generate_docs();
+        assert!(
+            DOCS.contains(&generated),
+            "the documentation of the kernel parameters is outdated, \
+             replace the list in `docs/src/kernel/kernel-parameters.md` with:\n{}",
+            generated
+        );
+    }
+}
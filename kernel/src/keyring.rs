@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The keys that are retained by the kernel, like the key retention service of Linux.
+//!
+//! The keys are added with `add_key` and used by the kernel, e.g., the encryption keys of the
+//! `crypt` targets of the mapped devices. Only the `user` and `logon` key types are supported,
+//! and the payloads of the keys cannot be read back from the user space.
+//!
+//! The keyrings are not implemented. All the keys are held in a global set, which is shared by
+//! all the special keyrings (e.g., the session keyring).
+
+use core::sync::atomic::{compiler_fence, AtomicI32, Ordering};
+
+use crate::prelude::*;
+
+/// The maximum size of the payload of a `user` or `logon` key.
+pub const MAX_PAYLOAD_SIZE: usize = 32767;
+
+/// The maximum length of the description of a key.
+pub const MAX_DESCRIPTION_LEN: usize = 4095;
+
+static KEYS: Mutex<Vec<Arc<Key>>> = Mutex::new(Vec::new());
+
+/// The serial number of the next key.
+///
+/// Like Linux, the serial numbers less than 3 are not used.
+static NEXT_SERIAL: AtomicI32 = AtomicI32::new(3);
+
+/// A key.
+pub struct Key {
+    serial: i32,
+    type_: KeyType,
+    description: String,
+    payload: Vec<u8>,
+}
+
+impl Key {
+    /// Returns the payload.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+}
+
+impl Debug for Key {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        // The payload is a secret.
+        f.debug_struct("Key")
+            .field("serial", &self.serial)
+            .field("type_", &self.type_)
+            .field("description", &self.description)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        // Clear the payload so that the secret does not remain in the freed memory.
+        self.payload.fill(0);
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+/// The type of a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    /// The key whose payload is an arbitrary blob.
+    User,
+    /// The same as `User`, except that the description must start with a prefix (e.g.,
+    /// `cifs:`).
+    Logon,
+}
+
+impl KeyType {
+    /// Parses the name of the type.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "user" => Ok(Self::User),
+            "logon" => Ok(Self::Logon),
+            // Like Linux, the types that start with a dot are internal ones.
+            _ if name.starts_with('.') => {
+                return_errno_with_message!(Errno::EPERM, "the key type cannot be used")
+            }
+            _ => return_errno_with_message!(Errno::ENODEV, "the key type is not supported"),
+        }
+    }
+}
+
+/// Adds a key and returns its serial number.
+///
+/// If a key with the same type and description exists, its payload is updated and its serial
+/// number is kept.
+pub fn add_key(type_: KeyType, description: &str, payload: Vec<u8>) -> Result<i32> {
+    if description.is_empty() || description.len() > MAX_DESCRIPTION_LEN {
+        return_errno_with_message!(Errno::EINVAL, "the key description is invalid");
+    }
+    if type_ == KeyType::Logon && description.find(':').is_none_or(|index| index == 0) {
+        return_errno_with_message!(Errno::EINVAL, "the logon key description has no prefix");
+    }
+    if payload.is_empty() || payload.len() > MAX_PAYLOAD_SIZE {
+        return_errno_with_message!(Errno::EINVAL, "the key payload size is invalid");
+    }
+
+    let mut keys = KEYS.lock();
+    let existing = keys
+        .iter()
+        .position(|key| key.type_ == type_ && key.description == description);
+    let serial = match existing {
+        Some(index) => keys.swap_remove(index).serial,
+        None => NEXT_SERIAL.fetch_add(1, Ordering::Relaxed),
+    };
+    keys.push(Arc::new(Key {
+        serial,
+        type_,
+        description: description.to_string(),
+        payload,
+    }));
+
+    Ok(serial)
+}
+
+/// Looks up the key with the type and description.
+pub fn lookup_key(type_: KeyType, description: &str) -> Option<Arc<Key>> {
+    KEYS.lock()
+        .iter()
+        .find(|key| key.type_ == type_ && key.description == description)
+        .cloned()
+}
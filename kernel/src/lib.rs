@@ -29,7 +29,6 @@
 #![register_tool(component_access_control)]
 
 use aster_framebuffer::FRAMEBUFFER_CONSOLE;
-use kcmdline::KCmdlineArg;
 use ostd::{
     arch::qemu::{exit_qemu, QemuExitCode},
     boot::boot_info,
@@ -48,8 +47,10 @@ extern crate controlled;
 extern crate getset;
 
 pub mod arch;
+mod bpf;
 pub mod context;
 pub mod cpu;
+mod crypto;
 pub mod device;
 pub mod driver;
 pub mod error;
@@ -57,9 +58,13 @@ pub mod events;
 pub mod fs;
 pub mod ipc;
 pub mod kcmdline;
+mod keyring;
 pub mod net;
+mod power;
 pub mod prelude;
 mod process;
+#[cfg(target_arch = "x86_64")]
+mod profiler;
 mod sched;
 pub mod syscall;
 pub mod thread;
@@ -67,6 +72,7 @@ pub mod time;
 mod util;
 pub(crate) mod vdso;
 pub mod vm;
+mod watchdog;
 
 #[ostd::main]
 #[controlled]
@@ -88,6 +94,7 @@ pub fn main() {
 }
 
 pub fn init() {
+    kcmdline::init();
     thread::init();
     util::random::init();
     driver::init();
@@ -97,9 +104,10 @@ pub fn init() {
     sched::init();
     fs::rootfs::init(boot_info().initramfs.expect("No initramfs found!")).unwrap();
     device::init().unwrap();
-    syscall::init();
     vdso::init();
     process::init();
+    power::init();
+    syscall::init();
 }
 
 fn ap_init() {
@@ -111,13 +119,14 @@ fn ap_init() {
 
         loop {
             crate::thread::Thread::yield_now();
-            ostd::cpu::sleep_for_interrupt();
+            sched::enter_idle();
         }
     }
     let preempt_guard = ostd::task::disable_preempt();
     let cpu_id = preempt_guard.current_cpu();
     drop(preempt_guard);
 
+    sched::init_on_ap();
     ThreadOptions::new(ap_idle_thread)
         .cpu_affinity(cpu_id.into())
         .sched_policy(SchedPolicy::Idle)
@@ -129,10 +138,18 @@ fn init_thread() {
     // Work queue should be initialized before interrupt is enabled,
     // in case any irq handler uses work queue as bottom half
     thread::work_queue::init();
+    thread::ksoftirqd::init();
+    thread::irq_thread::init();
+    // The profiler counts the samples in the work queue.
+    #[cfg(target_arch = "x86_64")]
+    profiler::init();
+    watchdog::init();
     #[cfg(target_arch = "x86_64")]
     net::lazy_init();
     fs::lazy_init();
+    device::lazy_init().unwrap();
     ipc::init();
+    vm::lazy_init();
     // driver::pci::virtio::block::block_device_test();
     let thread = ThreadOptions::new(|| {
         println!("[kernel] Hello world from kernel!");
@@ -149,7 +166,7 @@ fn init_thread() {
         console.disable();
     };
 
-    let karg: KCmdlineArg = boot_info().kernel_cmdline.as_str().into();
+    let karg = kcmdline::kcmdline();
 
     let initproc = spawn_init_process(
         karg.get_initproc_path().unwrap(),
@@ -160,9 +177,12 @@ fn init_thread() {
     // Wait till initproc become zombie.
     while !initproc.status().is_zombie() {
         crate::thread::Thread::yield_now();
-        ostd::cpu::sleep_for_interrupt();
+        sched::enter_idle();
     }
 
+    #[cfg(target_arch = "x86_64")]
+    profiler::dump();
+
     // TODO: exit via qemu isa debug device should not be the only way.
     let exit_code = if initproc.status().exit_code() == 0 {
         QemuExitCode::Success
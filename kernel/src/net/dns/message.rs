@@ -0,0 +1,256 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The encoding and decoding of DNS messages (RFC 1035, Section 4).
+
+use aster_bigtcp::wire::Ipv4Address;
+
+use crate::prelude::*;
+
+const HEADER_LEN: usize = 12;
+
+const FLAG_QR: u16 = 1 << 15;
+const FLAG_TC: u16 = 1 << 9;
+const FLAG_RD: u16 = 1 << 8;
+const RCODE_MASK: u16 = 0xf;
+
+const TYPE_A: u16 = 1;
+const CLASS_IN: u16 = 1;
+
+/// The maximum length of a domain name in the dotted form, without the trailing dot.
+const MAX_NAME_LEN: usize = 253;
+const MAX_LABEL_LEN: usize = 63;
+
+/// The two high bits that mark a compression pointer in place of a label length.
+const POINTER_MASK: u8 = 0xc0;
+
+/// Checks whether the domain name can be encoded in a query.
+pub(super) fn check_name(name: &str) -> Result<()> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return_errno_with_message!(Errno::EINVAL, "the domain name length is invalid");
+    }
+    if name
+        .split('.')
+        .any(|label| label.is_empty() || label.len() > MAX_LABEL_LEN)
+    {
+        return_errno_with_message!(Errno::EINVAL, "the domain name has an invalid label");
+    }
+    Ok(())
+}
+
+/// Encodes a recursive query for the `A` records of the domain name.
+pub(super) fn encode_query(id: u16, name: &str) -> Result<Vec<u8>> {
+    check_name(name)?;
+    let name = name.strip_suffix('.').unwrap_or(name);
+
+    let mut msg = Vec::with_capacity(HEADER_LEN + name.len() + 2 + 4);
+    msg.extend(id.to_be_bytes());
+    msg.extend(FLAG_RD.to_be_bytes());
+    // QDCOUNT, ANCOUNT, NSCOUNT, and ARCOUNT
+    msg.extend(1u16.to_be_bytes());
+    msg.extend([0; 6]);
+
+    for label in name.split('.') {
+        msg.push(label.len() as u8);
+        msg.extend(label.as_bytes());
+    }
+    msg.push(0);
+
+    msg.extend(TYPE_A.to_be_bytes());
+    msg.extend(CLASS_IN.to_be_bytes());
+    Ok(msg)
+}
+
+/// The response code of a DNS response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Rcode {
+    NoError,
+    NxDomain,
+    Other(u8),
+}
+
+impl From<u16> for Rcode {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => Self::NoError,
+            3 => Self::NxDomain,
+            other => Self::Other(other as u8),
+        }
+    }
+}
+
+/// A parsed DNS response.
+#[derive(Debug)]
+pub(super) struct Response {
+    pub(super) id: u16,
+    pub(super) rcode: Rcode,
+    pub(super) is_truncated: bool,
+    /// The addresses in the `A` records of the answer section.
+    ///
+    /// The owner names of the records are not checked, since the `A` records of the canonical
+    /// name follow the `CNAME` records if the queried name is an alias.
+    pub(super) addrs: Vec<Ipv4Address>,
+}
+
+impl Response {
+    /// Parses a response.
+    ///
+    /// If the response is truncated, the records that are cut off are silently ignored.
+    pub(super) fn parse(msg: &[u8]) -> Result<Self> {
+        let mut parser = Parser { msg, pos: 0 };
+
+        let id = parser.read_u16()?;
+        let flags = parser.read_u16()?;
+        let qd_count = parser.read_u16()?;
+        let an_count = parser.read_u16()?;
+        // NSCOUNT and ARCOUNT
+        parser.skip(4)?;
+
+        if flags & FLAG_QR == 0 {
+            return_errno_with_message!(Errno::EBADMSG, "the DNS message is not a response");
+        }
+        let is_truncated = flags & FLAG_TC != 0;
+
+        let mut addrs = Vec::new();
+        if let Err(err) = parser.parse_records(qd_count, an_count, &mut addrs) {
+            if !is_truncated {
+                return Err(err);
+            }
+        }
+
+        Ok(Self {
+            id,
+            rcode: Rcode::from(flags & RCODE_MASK),
+            is_truncated,
+            addrs,
+        })
+    }
+}
+
+struct Parser<'a> {
+    msg: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Parses the question section and the answer section, collecting the addresses in the
+    /// `A` records.
+    fn parse_records(
+        &mut self,
+        qd_count: u16,
+        an_count: u16,
+        addrs: &mut Vec<Ipv4Address>,
+    ) -> Result<()> {
+        for _ in 0..qd_count {
+            self.skip_name()?;
+            // QTYPE and QCLASS
+            self.skip(4)?;
+        }
+
+        for _ in 0..an_count {
+            self.skip_name()?;
+            let type_ = self.read_u16()?;
+            let class = self.read_u16()?;
+            // TTL
+            self.skip(4)?;
+            let data_len = self.read_u16()? as usize;
+            let data = self.read_bytes(data_len)?;
+            if let (TYPE_A, CLASS_IN, &[a, b, c, d]) = (type_, class, data) {
+                addrs.push(Ipv4Address::new(a, b, c, d));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let Some(bytes) = self.msg.get(self.pos..self.pos + len) else {
+            return_errno_with_message!(Errno::EBADMSG, "the DNS message is too short");
+        };
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn skip(&mut self, len: usize) -> Result<()> {
+        self.read_bytes(len).map(|_| ())
+    }
+
+    /// Skips a domain name, which ends with either a zero-length label or a compression pointer.
+    fn skip_name(&mut self) -> Result<()> {
+        loop {
+            let len = self.read_bytes(1)?[0];
+            match len & POINTER_MASK {
+                0 if len == 0 => return Ok(()),
+                0 => self.skip(len as usize)?,
+                POINTER_MASK => return self.skip(1),
+                _ => return_errno_with_message!(Errno::EBADMSG, "the label type is unknown"),
+            }
+        }
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn encode_a_query() {
+        let query = encode_query(0x1234, "nfs.example.").unwrap();
+        assert_eq!(
+            query,
+            b"\x12\x34\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00\
+              \x03nfs\x07example\x00\x00\x01\x00\x01"
+        );
+
+        assert!(encode_query(0, "").is_err());
+        assert!(encode_query(0, "a..b").is_err());
+        assert!(encode_query(0, &"a".repeat(MAX_LABEL_LEN + 1)).is_err());
+    }
+
+    #[ktest]
+    fn parse_compressed_response() {
+        let mut response = encode_query(0x1234, "www.example").unwrap();
+        // QR, RD, RA, and ANCOUNT = 2
+        response[2..4].copy_from_slice(&0x8180u16.to_be_bytes());
+        response[6..8].copy_from_slice(&2u16.to_be_bytes());
+        // www.example. CNAME example.
+        response.extend(b"\xc0\x0c\x00\x05\x00\x01\x00\x00\x00\x3c\x00\x02\xc0\x10");
+        // example. A 10.0.2.2
+        response.extend(b"\xc0\x10\x00\x01\x00\x01\x00\x00\x00\x3c\x00\x04\x0a\x00\x02\x02");
+
+        let parsed = Response::parse(&response).unwrap();
+        assert_eq!(parsed.id, 0x1234);
+        assert_eq!(parsed.rcode, Rcode::NoError);
+        assert!(!parsed.is_truncated);
+        assert_eq!(parsed.addrs, [Ipv4Address::new(10, 0, 2, 2)]);
+
+        // The last record is cut off.
+        assert!(Response::parse(&response[..response.len() - 1]).is_err());
+        response[2] |= (FLAG_TC >> 8) as u8;
+        let parsed = Response::parse(&response[..response.len() - 1]).unwrap();
+        assert!(parsed.is_truncated);
+        assert!(parsed.addrs.is_empty());
+    }
+
+    #[ktest]
+    fn parse_negative_response() {
+        let mut response = encode_query(0x1234, "missing.example").unwrap();
+        // QR, RD, RA, and NXDOMAIN
+        response[2..4].copy_from_slice(&0x8183u16.to_be_bytes());
+
+        let parsed = Response::parse(&response).unwrap();
+        assert_eq!(parsed.rcode, Rcode::NxDomain);
+        assert!(parsed.addrs.is_empty());
+
+        // A query is not a response.
+        let query = encode_query(0x1234, "missing.example").unwrap();
+        assert!(Response::parse(&query).is_err());
+    }
+}
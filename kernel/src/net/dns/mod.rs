@@ -0,0 +1,160 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A DNS client for kernel users.
+//!
+//! The client resolves host names to IPv4 addresses for the kernel components that initiate
+//! connections by themselves, e.g., mounting NFS shares by host names. The queries are sent over
+//! UDP to the name servers listed in `/etc/resolv.conf`, following the options there (see
+//! [`ResolvConf`]).
+//!
+//! Only the `A` records are queried. Truncated responses are not retried over TCP, so the client
+//! uses whatever addresses are contained in them.
+
+mod message;
+mod resolv_conf;
+
+use core::{net::Ipv4Addr, time::Duration};
+
+use aster_bigtcp::wire::Ipv4Address;
+
+use self::message::{Rcode, Response};
+pub use self::resolv_conf::ResolvConf;
+use crate::{
+    events::IoEvents,
+    net::{
+        namespace::NetNamespace,
+        socket::{ip::datagram::DatagramSocket, MessageHeader, SendRecvFlags, Socket, SocketAddr},
+    },
+    prelude::*,
+    process::signal::Pollable,
+    util::random::getrandom,
+};
+
+/// The well-known port of DNS.
+const DNS_PORT: u16 = 53;
+
+/// The maximum size of DNS messages over UDP (RFC 1035, Section 4.2.1).
+const MAX_UDP_MESSAGE_LEN: usize = 512;
+
+/// Resolves the host name to its IPv4 addresses.
+///
+/// The configuration is loaded from `/etc/resolv.conf` on every call, so the changes to the file
+/// take effect immediately. If the host name is an IPv4 address in the dotted-decimal notation,
+/// the address itself is returned without any queries.
+///
+/// # Errors
+///
+/// This function returns `ENOENT` if the host name does not exist or has no IPv4 addresses, and
+/// `ETIMEDOUT` if no name servers respond in time.
+pub fn resolve(hostname: &str) -> Result<Vec<Ipv4Address>> {
+    if let Some(addr) = parse_ipv4_addr(hostname) {
+        return Ok(vec![addr]);
+    }
+
+    resolve_with(&ResolvConf::load(), hostname)
+}
+
+/// Resolves the host name to its IPv4 addresses with the given configuration.
+///
+/// See [`resolve`] for details.
+pub fn resolve_with(conf: &ResolvConf, hostname: &str) -> Result<Vec<Ipv4Address>> {
+    if let Some(addr) = parse_ipv4_addr(hostname) {
+        return Ok(vec![addr]);
+    }
+    message::check_name(hostname)?;
+
+    let mut last_err = None;
+    for name in conf.candidate_names(hostname) {
+        match query_name(conf, &name) {
+            Ok(addrs) => return Ok(addrs),
+            // The name does not exist, but the name with the next search domain may.
+            Err(err) if err.error() == Errno::ENOENT => last_err = Some(err),
+            Err(err) => return Err(err),
+        }
+    }
+
+    Err(last_err
+        .unwrap_or_else(|| Error::with_message(Errno::ENOENT, "the host name does not exist")))
+}
+
+/// Queries the name servers for the IPv4 addresses of the fully qualified name.
+fn query_name(conf: &ResolvConf, name: &str) -> Result<Vec<Ipv4Address>> {
+    let mut id = [0u8; 2];
+    getrandom(&mut id)?;
+    let id = u16::from_ne_bytes(id);
+    let query = message::encode_query(id, name)?;
+
+    let mut last_err = None;
+    for _ in 0..conf.attempts() {
+        for server in conf.nameservers() {
+            match query_server(*server, &query, id, &conf.timeout()) {
+                Ok(addrs) => return Ok(addrs),
+                // A negative answer is authoritative, so asking other servers does not help.
+                Err(err) if err.error() == Errno::ENOENT => return Err(err),
+                Err(err) => {
+                    debug!("the DNS query to {:?} failed: {:?}", server, err);
+                    last_err = Some(err);
+                }
+            }
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| {
+        Error::with_message(Errno::ETIMEDOUT, "no name servers respond in time")
+    }))
+}
+
+/// Sends the query to the name server and waits for the response.
+fn query_server(
+    server: Ipv4Address,
+    query: &[u8],
+    id: u16,
+    timeout: &Duration,
+) -> Result<Vec<Ipv4Address>> {
+    // The name servers are queried in the initial network namespace, like other kernel services.
+    let socket = DatagramSocket::new(true, NetNamespace::get_init_singleton().clone());
+    socket.connect(SocketAddr::IPv4(server, DNS_PORT))?;
+    socket.sendmsg(
+        &mut VmReader::from(query).to_fallible(),
+        MessageHeader::new(None, Vec::new()),
+        SendRecvFlags::empty(),
+    )?;
+
+    let mut buf = vec![0u8; MAX_UDP_MESSAGE_LEN];
+    loop {
+        let recv_len = socket
+            .wait_events(IoEvents::IN, Some(timeout), || {
+                let mut writer = VmWriter::from(buf.as_mut_slice()).to_fallible();
+                socket
+                    .recvmsg(&mut writer, SendRecvFlags::empty())
+                    .map(|(len, _)| len)
+            })
+            .map_err(|err| match err.error() {
+                Errno::ETIME => Error::with_message(Errno::ETIMEDOUT, "the DNS query timed out"),
+                _ => err,
+            })?;
+
+        let response = match Response::parse(&buf[..recv_len]) {
+            Ok(response) if response.id == id => response,
+            // Ignore the responses to other queries and the garbage.
+            _ => continue,
+        };
+
+        let (errno, msg) = match response.rcode {
+            Rcode::NoError if !response.addrs.is_empty() => return Ok(response.addrs),
+            Rcode::NoError if response.is_truncated => {
+                (Errno::EMSGSIZE, "the DNS response is truncated")
+            }
+            Rcode::NoError => (Errno::ENOENT, "the host name has no IPv4 addresses"),
+            Rcode::NxDomain => (Errno::ENOENT, "the host name does not exist"),
+            Rcode::Other(_) => (Errno::EIO, "the name server fails to answer"),
+        };
+        return_errno_with_message!(errno, msg);
+    }
+}
+
+/// Parses an IPv4 address in the dotted-decimal notation.
+fn parse_ipv4_addr(s: &str) -> Option<Ipv4Address> {
+    let [a, b, c, d] = s.parse::<Ipv4Addr>().ok()?.octets();
+    Some(Ipv4Address::new(a, b, c, d))
+}
@@ -0,0 +1,245 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use aster_bigtcp::wire::Ipv4Address;
+
+use super::parse_ipv4_addr;
+use crate::{
+    fs::{
+        fs_resolver::{FsPath, FsResolver, AT_FDCWD},
+        utils::InodeType,
+    },
+    prelude::*,
+};
+
+/// The path of the resolver configuration file.
+const RESOLV_CONF_PATH: &str = "/etc/resolv.conf";
+
+/// The maximum size of the configuration file that is read.
+const MAX_FILE_SIZE: usize = 4096;
+
+// The limits and the defaults follow glibc.
+const MAX_NAMESERVERS: usize = 3;
+const MAX_SEARCH_DOMAINS: usize = 6;
+const DEFAULT_TIMEOUT_SECS: u64 = 5;
+const MAX_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_ATTEMPTS: usize = 2;
+const MAX_ATTEMPTS: usize = 5;
+const DEFAULT_NDOTS: usize = 1;
+const MAX_NDOTS: usize = 15;
+
+/// The configuration of the DNS resolver.
+///
+/// The configuration is in the format of `/etc/resolv.conf`, see resolv.conf(5). The supported
+/// keywords are `nameserver`, `search`, `domain`, and `options` with the `timeout:n`,
+/// `attempts:n`, and `ndots:n` options. Other keywords and options, as well as the IPv6 name
+/// servers, are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvConf {
+    nameservers: Vec<Ipv4Address>,
+    search: Vec<String>,
+    timeout: Duration,
+    attempts: usize,
+    ndots: usize,
+}
+
+impl Default for ResolvConf {
+    fn default() -> Self {
+        Self::parse("")
+    }
+}
+
+impl ResolvConf {
+    /// Loads the configuration from `/etc/resolv.conf`.
+    ///
+    /// If the file does not exist or cannot be read, the default configuration is returned,
+    /// which uses the name server on the local machine.
+    pub fn load() -> Self {
+        match read_conf_file() {
+            Ok(content) => Self::parse(&content),
+            Err(err) => {
+                debug!("failed to read {}: {:?}", RESOLV_CONF_PATH, err);
+                Self::default()
+            }
+        }
+    }
+
+    /// Parses the configuration.
+    ///
+    /// Malformed lines are ignored, as the resolver in libc does.
+    pub fn parse(content: &str) -> Self {
+        let mut conf = Self {
+            nameservers: Vec::new(),
+            search: Vec::new(),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            attempts: DEFAULT_ATTEMPTS,
+            ndots: DEFAULT_NDOTS,
+        };
+
+        for line in content.lines() {
+            let mut words = line.split_ascii_whitespace();
+            let Some(keyword) = words.next() else {
+                continue;
+            };
+            match keyword {
+                "nameserver" => {
+                    let addr = words.next().and_then(parse_ipv4_addr);
+                    if let Some(addr) = addr
+                        && conf.nameservers.len() < MAX_NAMESERVERS
+                    {
+                        conf.nameservers.push(addr);
+                    }
+                }
+                // The last one of the `domain` and `search` keywords takes effect.
+                "domain" => {
+                    conf.search.clear();
+                    conf.search.extend(words.next().map(normalize_domain));
+                }
+                "search" => {
+                    conf.search.clear();
+                    conf.search
+                        .extend(words.take(MAX_SEARCH_DOMAINS).map(normalize_domain));
+                }
+                "options" => words.for_each(|option| conf.parse_option(option)),
+                _ => (),
+            }
+        }
+
+        if conf.nameservers.is_empty() {
+            conf.nameservers.push(Ipv4Address::new(127, 0, 0, 1));
+        }
+        conf.search.retain(|domain| !domain.is_empty());
+
+        conf
+    }
+
+    fn parse_option(&mut self, option: &str) {
+        let Some((name, value)) = option.split_once(':') else {
+            return;
+        };
+        let Ok(value) = value.parse::<u64>() else {
+            return;
+        };
+        match name {
+            "timeout" => {
+                self.timeout = Duration::from_secs(value.clamp(1, MAX_TIMEOUT_SECS));
+            }
+            "attempts" => self.attempts = (value as usize).clamp(1, MAX_ATTEMPTS),
+            "ndots" => self.ndots = (value as usize).min(MAX_NDOTS),
+            _ => (),
+        }
+    }
+
+    /// Returns the name servers to query in order.
+    pub fn nameservers(&self) -> &[Ipv4Address] {
+        &self.nameservers
+    }
+
+    /// Returns the search list of the domains.
+    pub fn search(&self) -> &[String] {
+        &self.search
+    }
+
+    /// Returns the time to wait for a response from a name server.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns the number of rounds of querying all the name servers.
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+
+    /// Returns the fully qualified names to query in order for the host name.
+    ///
+    /// A name with a trailing dot is absolute and is queried as is. Otherwise, the name is
+    /// queried as is before the names with the search domains appended if it has at least
+    /// `ndots` dots, or after them if it has fewer dots.
+    pub(super) fn candidate_names(&self, hostname: &str) -> Vec<String> {
+        if hostname.ends_with('.') {
+            return vec![hostname.to_string()];
+        }
+
+        let with_domains = self
+            .search
+            .iter()
+            .map(|domain| format!("{}.{}", hostname, domain));
+        if hostname.matches('.').count() >= self.ndots {
+            core::iter::once(hostname.to_string())
+                .chain(with_domains)
+                .collect()
+        } else {
+            with_domains
+                .chain(core::iter::once(hostname.to_string()))
+                .collect()
+        }
+    }
+}
+
+fn normalize_domain(domain: &str) -> String {
+    domain.trim_end_matches('.').to_string()
+}
+
+fn read_conf_file() -> Result<String> {
+    let fs_path = FsPath::new(AT_FDCWD, RESOLV_CONF_PATH)?;
+    let inode = FsResolver::new().lookup(&fs_path)?.inode().clone();
+    if inode.type_() != InodeType::File {
+        return_errno_with_message!(Errno::EINVAL, "the configuration is not a regular file");
+    }
+
+    let mut buf = vec![0u8; inode.size().min(MAX_FILE_SIZE)];
+    let len = inode.read_bytes_at(0, &mut buf)?;
+    buf.truncate(len);
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn parse_resolv_conf() {
+        let conf = ResolvConf::parse(
+            "# Generated by DHCP\n\
+             nameserver 10.0.2.3\n\
+             nameserver fec0::3\n\
+             nameserver 8.8.8.8\n\
+             domain corp.example\n\
+             search lab.example. example\n\
+             options ndots:2 timeout:100 attempts:0 rotate\n",
+        );
+        assert_eq!(
+            conf.nameservers(),
+            [Ipv4Address::new(10, 0, 2, 3), Ipv4Address::new(8, 8, 8, 8)]
+        );
+        assert_eq!(conf.search(), ["lab.example", "example"]);
+        assert_eq!(conf.timeout(), Duration::from_secs(MAX_TIMEOUT_SECS));
+        assert_eq!(conf.attempts(), 1);
+
+        assert_eq!(
+            conf.candidate_names("nfs"),
+            ["nfs.lab.example", "nfs.example", "nfs"]
+        );
+        assert_eq!(
+            conf.candidate_names("nfs.lab.example"),
+            [
+                "nfs.lab.example",
+                "nfs.lab.example.lab.example",
+                "nfs.lab.example.example"
+            ]
+        );
+        assert_eq!(conf.candidate_names("nfs."), ["nfs."]);
+    }
+
+    #[ktest]
+    fn default_resolv_conf() {
+        let conf = ResolvConf::parse("nameserver not-an-address\n");
+        assert_eq!(conf, ResolvConf::default());
+        assert_eq!(conf.nameservers(), [Ipv4Address::new(127, 0, 0, 1)]);
+        assert!(conf.search().is_empty());
+        assert_eq!(conf.candidate_names("nfs"), ["nfs"]);
+    }
+}
@@ -1,7 +1,15 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::time::Duration;
+
 use super::sched::PollScheduler;
-use crate::net::socket::ip::{datagram::DatagramObserver, stream::StreamObserver};
+use crate::{
+    net::{
+        socket::ip::{datagram::DatagramObserver, stream::StreamObserver},
+        sysctl,
+    },
+    time::{clocks::RealTimeClock, Clock},
+};
 
 pub struct BigtcpExt;
 
@@ -10,4 +18,12 @@ impl aster_bigtcp::ext::Ext for BigtcpExt {
 
     type TcpEventObserver = StreamObserver;
     type UdpEventObserver = DatagramObserver;
+
+    fn now() -> Duration {
+        RealTimeClock::get().read_time()
+    }
+
+    fn max_time_wait_conns() -> usize {
+        sysctl::tcp_max_tw_buckets()
+    }
 }
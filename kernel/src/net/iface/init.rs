@@ -1,56 +1,39 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use alloc::{borrow::ToOwned, sync::Arc};
-use core::slice::Iter;
 
 use aster_bigtcp::{
     device::WithDevice,
     iface::{InterfaceFlags, InterfaceType},
 };
 use aster_softirq::BottomHalfDisabled;
-use spin::Once;
 
 use super::{poll::poll_ifaces, Iface};
-use crate::{net::iface::sched::PollScheduler, prelude::*};
-
-static IFACES: Once<Vec<Arc<Iface>>> = Once::new();
-
-pub fn loopback_iface() -> &'static Arc<Iface> {
-    &IFACES.get().unwrap()[0]
-}
-
-pub fn virtio_iface() -> Option<&'static Arc<Iface>> {
-    IFACES.get().unwrap().get(1)
-}
-
-pub fn iter_all_ifaces() -> Iter<'static, Arc<Iface>> {
-    IFACES.get().unwrap().iter()
-}
+use crate::{
+    net::{iface::sched::PollScheduler, namespace::NetNamespace},
+    prelude::*,
+};
 
 pub fn init() {
-    IFACES.call_once(|| {
-        let mut ifaces = Vec::with_capacity(2);
-
-        // Initialize loopback before virtio
-        // to ensure the loopback interface index is ahead of virtio.
-        ifaces.push(new_loopback());
+    let mut ifaces = Vec::with_capacity(2);
 
-        if let Some(iface_virtio) = new_virtio() {
-            ifaces.push(iface_virtio);
-        }
-
-        ifaces
-    });
+    // Initialize loopback before virtio
+    // to ensure the loopback interface index is ahead of virtio.
+    ifaces.push(new_loopback());
 
-    if let Some(iface_virtio) = virtio_iface() {
+    if let Some(iface_virtio) = new_virtio() {
         for (name, _) in aster_network::all_devices() {
             // TODO: further check that the irq num is the same as iface's irq num
-            let callback = || iface_virtio.poll();
-            aster_network::register_recv_callback(&name, callback);
+            let iface = iface_virtio.clone();
+            let callback = move || iface.poll();
+            aster_network::register_recv_callback(&name, callback.clone());
             aster_network::register_send_callback(&name, callback);
         }
+        ifaces.push(iface_virtio);
     }
 
+    NetNamespace::init(ifaces);
+
     poll_ifaces();
 }
 
@@ -95,15 +78,15 @@ fn new_virtio() -> Option<Arc<Iface>> {
     Some(EtherIface::new(
         Wrapper(virtio_net),
         EthernetAddress(ether_addr),
-        Ipv4Cidr::new(VIRTIO_ADDRESS, VIRTIO_ADDRESS_PREFIX_LEN),
-        VIRTIO_GATEWAY,
+        Some(Ipv4Cidr::new(VIRTIO_ADDRESS, VIRTIO_ADDRESS_PREFIX_LEN)),
+        Some(VIRTIO_GATEWAY),
         "eth0".to_owned(),
         PollScheduler::new(),
         flags,
     ))
 }
 
-fn new_loopback() -> Arc<Iface> {
+pub(in crate::net) fn new_loopback() -> Arc<Iface> {
     use aster_bigtcp::{
         device::{Loopback, Medium},
         iface::IpIface,
@@ -6,37 +6,51 @@ use core::time::Duration;
 use log::trace;
 use ostd::timer::Jiffies;
 
-use super::{iter_all_ifaces, Iface};
+use super::Iface;
 use crate::{
+    net::namespace::NetNamespace,
     sched::{Nice, SchedPolicy},
     thread::kernel_thread::ThreadOptions,
     WaitTimeout,
 };
 
 pub fn lazy_init() {
-    for iface in iter_all_ifaces() {
+    for iface in NetNamespace::get_init_singleton().ifaces().iter() {
         spawn_background_poll_thread(iface.clone());
     }
 }
 
 pub(super) fn poll_ifaces() {
-    for iface in iter_all_ifaces() {
+    for iface in NetNamespace::get_init_singleton().ifaces().iter() {
         iface.poll();
     }
 }
 
-fn spawn_background_poll_thread(iface: Arc<Iface>) {
+/// Spawns a thread that polls the interface in the background, until the polling is stopped by
+/// [`PollScheduler::stop`].
+///
+/// [`PollScheduler::stop`]: super::sched::PollScheduler::stop
+pub(in crate::net) fn spawn_background_poll_thread(iface: Arc<Iface>) {
     let task_fn = move || {
         trace!("spawn background poll thread for {}", iface.name());
 
         let sched_poll = iface.sched_poll();
         let wait_queue = sched_poll.polling_wait_queue();
 
-        loop {
+        while !sched_poll.is_stopped() {
             let next_poll_at_ms = if let Some(next_poll_at_ms) = sched_poll.next_poll_at_ms() {
                 next_poll_at_ms
             } else {
-                wait_queue.wait_until(|| sched_poll.next_poll_at_ms())
+                // The inner `Option` is `None` if the polling is stopped.
+                let Some(next_poll_at_ms) = wait_queue.wait_until(|| {
+                    if sched_poll.is_stopped() {
+                        return Some(None);
+                    }
+                    sched_poll.next_poll_at_ms().map(Some)
+                }) else {
+                    break;
+                };
+                next_poll_at_ms
             };
 
             let now_as_ms = Jiffies::elapsed().as_duration().as_millis() as u64;
@@ -50,6 +64,7 @@ fn spawn_background_poll_thread(iface: Arc<Iface>) {
             // For a more in-depth discussion, please refer to the following link:
             // <https://github.com/asterinas/asterinas/pull/630#discussion_r1496817030>.
             if now_as_ms >= next_poll_at_ms {
+                sched_poll.clear_poll_request();
                 iface.poll();
                 continue;
             }
@@ -58,7 +73,10 @@ fn spawn_background_poll_thread(iface: Arc<Iface>) {
             let _ = wait_queue.wait_until_or_timeout(
                 // If `sched_poll.next_poll_at_ms()` changes to an earlier time, we will end the
                 // waiting.
-                || (sched_poll.next_poll_at_ms()? < next_poll_at_ms).then_some(()),
+                || {
+                    (sched_poll.is_stopped() || sched_poll.next_poll_at_ms()? < next_poll_at_ms)
+                        .then_some(())
+                },
                 &duration,
             );
         }
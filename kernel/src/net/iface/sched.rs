@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::sync::atomic::{AtomicU64, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
 use aster_bigtcp::iface::ScheduleNextPoll;
 use ostd::sync::WaitQueue;
@@ -11,6 +11,10 @@ pub struct PollScheduler {
     next_poll_at_ms: AtomicU64,
     /// The wait queue that the background polling thread will sleep on.
     polling_wait_queue: WaitQueue,
+    /// Whether a poll is requested to be done as soon as possible.
+    is_poll_requested: AtomicBool,
+    /// Whether the background polling thread should exit.
+    is_stopped: AtomicBool,
 }
 
 impl PollScheduler {
@@ -18,10 +22,16 @@ impl PollScheduler {
         Self {
             next_poll_at_ms: AtomicU64::new(0),
             polling_wait_queue: WaitQueue::new(),
+            is_poll_requested: AtomicBool::new(false),
+            is_stopped: AtomicBool::new(false),
         }
     }
 
     pub(super) fn next_poll_at_ms(&self) -> Option<u64> {
+        if self.is_poll_requested.load(Ordering::Relaxed) {
+            return Some(0);
+        }
+
         let millis = self.next_poll_at_ms.load(Ordering::Relaxed);
         if millis == 0 {
             None
@@ -33,6 +43,34 @@ impl PollScheduler {
     pub(super) fn polling_wait_queue(&self) -> &WaitQueue {
         &self.polling_wait_queue
     }
+
+    /// Requests a poll to be done as soon as possible.
+    ///
+    /// Unlike [`ScheduleNextPoll::schedule_next_poll`], the request is not overridden when the
+    /// poll in progress finishes. So it can be used when a virtual device receives a frame,
+    /// which may happen while the interface is being polled.
+    pub(in crate::net) fn request_poll(&self) {
+        self.is_poll_requested.store(true, Ordering::Release);
+        self.polling_wait_queue.wake_all();
+    }
+
+    /// Clears the poll request, which should be done just before polling.
+    pub(super) fn clear_poll_request(&self) {
+        // This synchronizes with the `Release` store in `request_poll`, so that the poll will see
+        // the frames received before the request.
+        self.is_poll_requested.swap(false, Ordering::Acquire);
+    }
+
+    /// Stops the background polling thread, e.g., when the network namespace of the interface is
+    /// destroyed.
+    pub(in crate::net) fn stop(&self) {
+        self.is_stopped.store(true, Ordering::Relaxed);
+        self.polling_wait_queue.wake_all();
+    }
+
+    pub(super) fn is_stopped(&self) -> bool {
+        self.is_stopped.load(Ordering::Relaxed)
+    }
 }
 
 impl ScheduleNextPoll for PollScheduler {
@@ -0,0 +1,165 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use aster_bigtcp::wire::{EthernetAddress, EthernetFrame};
+use ostd::timer::Jiffies;
+
+use super::device::{random_ether_addr, Kind, VirtDevice};
+use crate::{net::namespace::NetNamespace, prelude::*};
+
+/// The state of a bridge device.
+pub(super) struct Bridge {
+    /// The devices that are attached to the bridge.
+    ports: SpinLock<Vec<Weak<VirtDevice>>>,
+    /// The forwarding database (FDB), which maps the addresses to the ports that they are
+    /// learned from.
+    fdb: SpinLock<BTreeMap<EthernetAddress, FdbEntry>>,
+    /// The time after which a learned entry expires.
+    ageing_time: Duration,
+}
+
+struct FdbEntry {
+    port: Weak<VirtDevice>,
+    /// The time when the entry is learned, since the system booted.
+    updated_at: Duration,
+}
+
+/// The default ageing time of the FDB entries.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/bridge/br_private.h>
+const DEFAULT_AGEING_TIME: Duration = Duration::from_secs(300);
+
+/// The maximum number of the FDB entries.
+///
+/// If the FDB is full, the addresses of the new hosts will not be learned, so the frames to them
+/// will be flooded to all ports.
+const MAX_FDB_ENTRIES: usize = 4096;
+
+impl Bridge {
+    /// Forwards a frame to the ports.
+    ///
+    /// `in_port` is the port that the frame is received from, or `None` if the frame is sent by
+    /// the bridge itself. This method returns whether the frame should also be received by the
+    /// bridge itself.
+    pub(super) fn forward(
+        &self,
+        bridge_addr: EthernetAddress,
+        in_port: Option<&Arc<VirtDevice>>,
+        data: &[u8],
+    ) -> bool {
+        let Ok(frame) = EthernetFrame::new_checked(data) else {
+            return false;
+        };
+        let src_addr = frame.src_addr();
+        let dst_addr = frame.dst_addr();
+        let now = Jiffies::elapsed().as_duration();
+
+        if let Some(in_port) = in_port
+            && src_addr.is_unicast()
+        {
+            self.learn(src_addr, in_port, now);
+        }
+
+        let is_in_port = |port: &Arc<VirtDevice>| in_port.is_some_and(|p| Arc::ptr_eq(p, port));
+
+        if dst_addr.is_unicast() {
+            if dst_addr == bridge_addr {
+                return in_port.is_some();
+            }
+            if let Some(out_port) = self.lookup(dst_addr, now) {
+                if !is_in_port(&out_port) {
+                    out_port.transmit(data.to_vec());
+                }
+                return false;
+            }
+        }
+
+        // Flood the frame if the destination is a broadcast address, a multicast address, or an
+        // unknown unicast address.
+        let out_ports: Vec<_> = self
+            .ports
+            .lock()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter(|port| !is_in_port(port))
+            .collect();
+        for out_port in out_ports {
+            out_port.transmit(data.to_vec());
+        }
+
+        in_port.is_some() && !dst_addr.is_unicast()
+    }
+
+    fn learn(&self, addr: EthernetAddress, port: &Arc<VirtDevice>, now: Duration) {
+        let mut fdb = self.fdb.lock();
+
+        if fdb.len() >= MAX_FDB_ENTRIES && !fdb.contains_key(&addr) {
+            fdb.retain(|_, entry| !self.is_expired(entry, now));
+            if fdb.len() >= MAX_FDB_ENTRIES {
+                return;
+            }
+        }
+
+        fdb.insert(
+            addr,
+            FdbEntry {
+                port: Arc::downgrade(port),
+                updated_at: now,
+            },
+        );
+    }
+
+    fn lookup(&self, addr: EthernetAddress, now: Duration) -> Option<Arc<VirtDevice>> {
+        let fdb = self.fdb.lock();
+        let entry = fdb.get(&addr)?;
+        if self.is_expired(entry, now) {
+            return None;
+        }
+        entry.port.upgrade()
+    }
+
+    fn is_expired(&self, entry: &FdbEntry, now: Duration) -> bool {
+        now.saturating_sub(entry.updated_at) >= self.ageing_time
+    }
+
+    pub(super) fn add_port(&self, port: &Arc<VirtDevice>) {
+        self.ports.lock().push(Arc::downgrade(port));
+    }
+
+    /// Removes the port and the FDB entries learned from it.
+    pub(super) fn remove_port(&self, port: &Arc<VirtDevice>) {
+        let is_port = |weak: &Weak<VirtDevice>| weak.as_ptr() == Arc::as_ptr(port);
+
+        self.ports.lock().retain(|weak| !is_port(weak));
+        self.fdb.lock().retain(|_, entry| !is_port(&entry.port));
+    }
+
+    /// Removes all the ports and the FDB entries, and returns the removed ports.
+    pub(super) fn take_ports(&self) -> Vec<Arc<VirtDevice>> {
+        self.fdb.lock().clear();
+
+        let ports = core::mem::take(&mut *self.ports.lock());
+        ports.iter().filter_map(Weak::upgrade).collect()
+    }
+}
+
+/// Creates a bridge and adds it to the network namespace.
+///
+/// If `ageing_time` is `None`, the default ageing time of the FDB entries is used.
+pub fn new_bridge(
+    name: String,
+    net_ns: &Arc<NetNamespace>,
+    ageing_time: Option<Duration>,
+) -> Result<Arc<VirtDevice>> {
+    let bridge = Bridge {
+        ports: SpinLock::new(Vec::new()),
+        fdb: SpinLock::new(BTreeMap::new()),
+        ageing_time: ageing_time.unwrap_or(DEFAULT_AGEING_TIME),
+    };
+    let device = Arc::new(VirtDevice::new(Kind::Bridge(bridge), random_ether_addr()?));
+
+    device.register(name, net_ns)?;
+
+    Ok(device)
+}
@@ -0,0 +1,408 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_bigtcp::{
+    device::{Device, DeviceCapabilities, Medium, NotifyDevice, RxToken, TxToken, WithDevice},
+    iface::{EtherIface, InterfaceFlags},
+    time::Instant,
+    wire::EthernetAddress,
+};
+use spin::Once;
+
+use super::{bridge::Bridge, veth::Veth};
+use crate::{
+    net::{
+        iface::{sched::PollScheduler, spawn_background_poll_thread, Iface},
+        namespace::NetNamespace,
+    },
+    prelude::*,
+    util::random::getrandom,
+};
+
+/// A virtual network device.
+pub struct VirtDevice {
+    kind: Kind,
+    ether_addr: EthernetAddress,
+    /// The received frames that are waiting to be polled by the interface.
+    rx_queue: SpinLock<VecDeque<RxFrame>>,
+    /// The interface of the device.
+    iface: Once<Weak<Iface>>,
+    /// The bridge that the device is attached to.
+    master: SpinLock<Weak<VirtDevice>>,
+    /// The network namespace that the interface is in.
+    net_ns: SpinLock<Weak<NetNamespace>>,
+}
+
+pub(super) enum Kind {
+    Veth(Veth),
+    Bridge(Bridge),
+}
+
+/// The kind of a virtual network device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VirtDeviceKind {
+    Veth,
+    Bridge,
+}
+
+impl VirtDeviceKind {
+    /// Returns the name of the kind, which is used in netlink (i.e., `IFLA_INFO_KIND`).
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Veth => "veth",
+            Self::Bridge => "bridge",
+        }
+    }
+
+    /// Returns the kind with the name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "veth" => Some(Self::Veth),
+            "bridge" => Some(Self::Bridge),
+            _ => None,
+        }
+    }
+}
+
+struct RxFrame {
+    data: Vec<u8>,
+    /// The port that the frame is received from, if the frame is received by a bridge.
+    port: Option<Arc<VirtDevice>>,
+}
+
+/// The maximum number of the frames in a receive queue.
+///
+/// This is the default length of the transmit queues in Linux. The frames are dropped if the
+/// queue is full.
+const RX_QUEUE_LEN: usize = 1000;
+
+/// The maximum transmission unit, including the Ethernet header.
+const MAX_TRANSMISSION_UNIT: usize = 1514;
+
+/// The virtual devices, indexed by the indexes of their interfaces.
+static VIRT_DEVICES: SpinLock<BTreeMap<u32, Arc<VirtDevice>>> = SpinLock::new(BTreeMap::new());
+
+impl VirtDevice {
+    pub(super) fn new(kind: Kind, ether_addr: EthernetAddress) -> Self {
+        Self {
+            kind,
+            ether_addr,
+            rx_queue: SpinLock::new(VecDeque::new()),
+            iface: Once::new(),
+            master: SpinLock::new(Weak::new()),
+            net_ns: SpinLock::new(Weak::new()),
+        }
+    }
+
+    /// Creates the interface of the device and adds it to the network namespace.
+    pub(super) fn register(
+        self: &Arc<Self>,
+        name: String,
+        net_ns: &Arc<NetNamespace>,
+    ) -> Result<()> {
+        struct Wrapper(Mutex<Handle>);
+
+        impl WithDevice for Wrapper {
+            type Device = Handle;
+
+            fn with<F, R>(&self, f: F) -> R
+            where
+                F: FnOnce(&mut Self::Device) -> R,
+            {
+                let mut device = self.0.lock();
+                f(&mut device)
+            }
+        }
+
+        // FIXME: The flags cannot be changed yet, so the devices are always up.
+        let flags = InterfaceFlags::UP
+            | InterfaceFlags::BROADCAST
+            | InterfaceFlags::RUNNING
+            | InterfaceFlags::MULTICAST
+            | InterfaceFlags::LOWER_UP;
+
+        let iface = EtherIface::new(
+            Wrapper(Mutex::new(Handle(self.clone()))),
+            self.ether_addr,
+            None,
+            None,
+            name,
+            PollScheduler::new(),
+            flags,
+        ) as Arc<Iface>;
+
+        net_ns.add_iface(iface.clone())?;
+        self.iface.call_once(|| Arc::downgrade(&iface));
+        *self.net_ns.lock() = Arc::downgrade(net_ns);
+        VIRT_DEVICES.lock().insert(iface.index(), self.clone());
+
+        spawn_background_poll_thread(iface);
+
+        Ok(())
+    }
+
+    /// Returns the virtual device of the interface, if the interface is virtual.
+    pub fn from_iface(iface: &Iface) -> Option<Arc<Self>> {
+        VIRT_DEVICES.lock().get(&iface.index()).cloned()
+    }
+
+    /// Returns the kind of the device.
+    pub fn kind(&self) -> VirtDeviceKind {
+        match &self.kind {
+            Kind::Veth(_) => VirtDeviceKind::Veth,
+            Kind::Bridge(_) => VirtDeviceKind::Bridge,
+        }
+    }
+
+    /// Returns the interface of the device.
+    ///
+    /// This method returns `None` if the device has been destroyed.
+    pub fn iface(&self) -> Option<Arc<Iface>> {
+        self.iface.get()?.upgrade()
+    }
+
+    /// Returns the other end of the veth pair, if the device is a veth device.
+    pub fn peer(&self) -> Option<Arc<VirtDevice>> {
+        match &self.kind {
+            Kind::Veth(veth) => veth.peer(),
+            Kind::Bridge(_) => None,
+        }
+    }
+
+    /// Returns the bridge that the device is attached to.
+    pub fn master(&self) -> Option<Arc<VirtDevice>> {
+        self.master.lock().upgrade()
+    }
+
+    /// Attaches the device to the bridge as a port of it.
+    ///
+    /// If the device is attached to another bridge, it will be detached from that bridge first.
+    pub fn set_master(self: &Arc<Self>, master: &Arc<VirtDevice>) -> Result<()> {
+        let Kind::Bridge(bridge) = &master.kind else {
+            return_errno_with_message!(Errno::EINVAL, "the master device is not a bridge");
+        };
+        if matches!(self.kind, Kind::Bridge(_)) {
+            return_errno_with_message!(Errno::ELOOP, "a bridge cannot be attached to a bridge");
+        }
+
+        if self.master().is_some_and(|old| Arc::ptr_eq(&old, master)) {
+            return Ok(());
+        }
+        self.detach();
+
+        *self.master.lock() = Arc::downgrade(master);
+        bridge.add_port(self);
+
+        Ok(())
+    }
+
+    /// Detaches the device from the bridge that it is attached to, if any.
+    pub fn detach(self: &Arc<Self>) {
+        let old_master = core::mem::take(&mut *self.master.lock());
+        if let Some(old_master) = old_master.upgrade()
+            && let Kind::Bridge(bridge) = &old_master.kind
+        {
+            bridge.remove_port(self);
+        }
+    }
+
+    /// Moves the interface of the device to the network namespace.
+    ///
+    /// As in Linux, the device is detached from its bridge and the addresses of the interface
+    /// are removed.
+    pub fn move_to(self: &Arc<Self>, net_ns: &Arc<NetNamespace>) -> Result<()> {
+        let Some(iface) = self.iface() else {
+            return_errno_with_message!(Errno::ENODEV, "the device has been destroyed");
+        };
+        let old_net_ns = self.net_ns.lock().upgrade();
+        if old_net_ns
+            .as_ref()
+            .is_some_and(|old_net_ns| Arc::ptr_eq(old_net_ns, net_ns))
+        {
+            return Ok(());
+        }
+
+        if matches!(self.kind, Kind::Bridge(_)) {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "a bridge cannot be moved to another network namespace"
+            );
+        }
+
+        net_ns.add_iface(iface.clone())?;
+        if let Some(old_net_ns) = old_net_ns {
+            old_net_ns.remove_iface(iface.index());
+        }
+        *self.net_ns.lock() = Arc::downgrade(net_ns);
+
+        self.detach();
+        iface.set_ipv4_cidr(None);
+
+        Ok(())
+    }
+
+    /// Destroys the device, which removes its interface from its network namespace.
+    ///
+    /// If the device is one end of a veth pair, the other end is also destroyed.
+    pub fn destroy(self: &Arc<Self>) {
+        if let Some(peer) = self.peer() {
+            peer.unregister();
+        }
+        self.unregister();
+    }
+
+    fn unregister(self: &Arc<Self>) {
+        match &self.kind {
+            Kind::Veth(veth) => veth.clear_peer(),
+            Kind::Bridge(bridge) => {
+                for port in bridge.take_ports() {
+                    *port.master.lock() = Weak::new();
+                }
+            }
+        }
+        self.detach();
+
+        if let Some(iface) = self.iface() {
+            // The namespace is gone if it is being dropped, in which case the interface has
+            // been removed with it.
+            let net_ns = self.net_ns.lock().upgrade();
+            if let Some(net_ns) = net_ns {
+                net_ns.remove_iface(iface.index());
+            }
+            iface.sched_poll().stop();
+            VIRT_DEVICES.lock().remove(&iface.index());
+        }
+
+        self.rx_queue.lock().clear();
+    }
+}
+
+impl VirtDevice {
+    /// Transmits a frame from the device.
+    pub(super) fn transmit(self: &Arc<Self>, frame: Vec<u8>) {
+        match &self.kind {
+            Kind::Veth(veth) => {
+                if let Some(peer) = veth.peer() {
+                    peer.receive(frame);
+                }
+            }
+            Kind::Bridge(bridge) => {
+                // The frames sent by the bridge itself are never received by the bridge.
+                bridge.forward(self.ether_addr, None, &frame);
+            }
+        }
+    }
+
+    /// Receives a frame on the device.
+    ///
+    /// If the device is attached to a bridge, the frame is received by the bridge instead.
+    fn receive(self: &Arc<Self>, data: Vec<u8>) {
+        if let Some(master) = self.master() {
+            master.enqueue(RxFrame {
+                data,
+                port: Some(self.clone()),
+            });
+        } else {
+            self.enqueue(RxFrame { data, port: None });
+        }
+    }
+
+    fn enqueue(&self, frame: RxFrame) {
+        {
+            let mut rx_queue = self.rx_queue.lock();
+            if rx_queue.len() >= RX_QUEUE_LEN {
+                return;
+            }
+            rx_queue.push_back(frame);
+        }
+
+        if let Some(iface) = self.iface() {
+            iface.sched_poll().request_poll();
+        }
+    }
+
+    /// Pops a frame that should be processed by the interface.
+    ///
+    /// For a bridge, the frames received from its ports are forwarded here, and only the ones
+    /// that are sent to the bridge itself are returned.
+    fn pop_rx_frame(&self) -> Option<Vec<u8>> {
+        loop {
+            let RxFrame { data, port } = self.rx_queue.lock().pop_front()?;
+
+            let Kind::Bridge(bridge) = &self.kind else {
+                return Some(data);
+            };
+            let Some(port) = port else {
+                return Some(data);
+            };
+            if bridge.forward(self.ether_addr, Some(&port), &data) {
+                return Some(data);
+            }
+        }
+    }
+}
+
+/// Generates a random address that is unicast and locally administered.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/linux/etherdevice.h>
+pub(super) fn random_ether_addr() -> Result<EthernetAddress> {
+    let mut addr = [0u8; 6];
+    getrandom(&mut addr)?;
+    // Clear the multicast bit and set the locally administered bit.
+    addr[0] &= 0xfe;
+    addr[0] |= 0x02;
+    Ok(EthernetAddress(addr))
+}
+
+/// A handle through which the interface transmits and receives the frames of the device.
+struct Handle(Arc<VirtDevice>);
+
+impl Device for Handle {
+    type RxToken<'a> = VirtRxToken;
+    type TxToken<'a> = VirtTxToken<'a>;
+
+    fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let frame = self.0.pop_rx_frame()?;
+        Some((VirtRxToken(frame), VirtTxToken(&self.0)))
+    }
+
+    fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
+        Some(VirtTxToken(&self.0))
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let mut caps = DeviceCapabilities::default();
+        caps.max_burst_size = None;
+        caps.medium = Medium::Ethernet;
+        caps.max_transmission_unit = MAX_TRANSMISSION_UNIT;
+        caps
+    }
+}
+
+impl NotifyDevice for Handle {
+    fn notify_poll_end(&mut self) {}
+}
+
+struct VirtRxToken(Vec<u8>);
+
+impl RxToken for VirtRxToken {
+    fn consume<R, F>(self, f: F) -> R
+    where
+        F: FnOnce(&[u8]) -> R,
+    {
+        f(&self.0)
+    }
+}
+
+struct VirtTxToken<'a>(&'a Arc<VirtDevice>);
+
+impl TxToken for VirtTxToken<'_> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let mut buffer = vec![0u8; len];
+        let res = f(&mut buffer);
+        self.0.transmit(buffer);
+        res
+    }
+}
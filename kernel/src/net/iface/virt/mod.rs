@@ -0,0 +1,25 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Virtual network devices.
+//!
+//! The virtual devices pass Ethernet frames in memory. A frame transmitted by a device is put
+//! into the receive queue of another device, and the interface of that device is requested to
+//! poll it later. So a frame never goes through the devices recursively, even if the devices are
+//! connected as a loop.
+//!
+//! The following devices are supported:
+//!  - A veth pair, where the frames transmitted on one end are received on the other end. The
+//!    two ends can be in different network namespaces, which connects the namespaces together.
+//!  - A bridge, which forwards the frames among the devices attached to it (i.e., its ports)
+//!    according to its forwarding database (FDB). The FDB is learned from the source addresses
+//!    of the received frames.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man4/veth.4.html>
+
+mod bridge;
+mod device;
+mod veth;
+
+pub use bridge::new_bridge;
+pub use device::{VirtDevice, VirtDeviceKind};
+pub use veth::new_veth_pair;
@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::device::{random_ether_addr, Kind, VirtDevice};
+use crate::{net::namespace::NetNamespace, prelude::*};
+
+/// The state of a veth device.
+pub(super) struct Veth {
+    /// The other end of the veth pair.
+    peer: SpinLock<Weak<VirtDevice>>,
+}
+
+impl Veth {
+    fn new(peer: Weak<VirtDevice>) -> Self {
+        Self {
+            peer: SpinLock::new(peer),
+        }
+    }
+
+    pub(super) fn peer(&self) -> Option<Arc<VirtDevice>> {
+        self.peer.lock().upgrade()
+    }
+
+    /// Disconnects the device from the other end, so the frames will no longer be delivered.
+    pub(super) fn clear_peer(&self) {
+        *self.peer.lock() = Weak::new();
+    }
+}
+
+/// Creates a veth pair.
+///
+/// One end is named `name` and added to `net_ns`, and the other end is named `peer_name` and
+/// added to `peer_net_ns`. The end named `name` is returned.
+pub fn new_veth_pair(
+    name: String,
+    net_ns: &Arc<NetNamespace>,
+    peer_name: String,
+    peer_net_ns: &Arc<NetNamespace>,
+) -> Result<Arc<VirtDevice>> {
+    let ether_addr = random_ether_addr()?;
+    let peer_ether_addr = random_ether_addr()?;
+
+    let mut peer = None;
+    let device = Arc::new_cyclic(|weak_device| {
+        let peer_device = Arc::new(VirtDevice::new(
+            Kind::Veth(Veth::new(weak_device.clone())),
+            peer_ether_addr,
+        ));
+        let device = VirtDevice::new(
+            Kind::Veth(Veth::new(Arc::downgrade(&peer_device))),
+            ether_addr,
+        );
+        peer = Some(peer_device);
+        device
+    });
+    let peer = peer.unwrap();
+
+    device.register(name, net_ns)?;
+    if let Err(err) = peer.register(peer_name, peer_net_ns) {
+        device.destroy();
+        return Err(err);
+    }
+
+    Ok(device)
+}
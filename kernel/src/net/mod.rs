@@ -1,11 +1,13 @@
 // SPDX-License-Identifier: MPL-2.0
 
+pub mod dns;
 pub mod iface;
+pub mod namespace;
 pub mod socket;
+pub mod sysctl;
 
 pub fn init() {
     iface::init();
-    socket::netlink::init();
     socket::vsock::init();
 }
 
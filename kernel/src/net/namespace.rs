@@ -0,0 +1,164 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Network namespaces.
+//!
+//! A network namespace isolates the network interfaces and the IP sockets. A socket belongs to
+//! the network namespace of the process that creates it, and it can only be bound to the
+//! addresses of the interfaces in that namespace. The netlink route requests also see and
+//! change the interfaces in the namespace of the requesting socket.
+//!
+//! The initial namespace has the loopback interface and the virtio-net interface, if any. A
+//! new namespace is created by `unshare(CLONE_NEWNET)` or `clone(CLONE_NEWNET)` with a loopback
+//! interface of its own. The virtual interfaces (see [`super::iface::virt`]) can be added to a
+//! namespace and moved between namespaces, which connects the namespaces together.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man7/network_namespaces.7.html>
+
+use aster_bigtcp::wire::Ipv4Address;
+use ostd::sync::{PreemptDisabled, RwLockReadGuard};
+use spin::Once;
+
+use super::iface::{self, virt::VirtDevice, Iface};
+use crate::{prelude::*, process::namespace::user_ns::UserNamespace};
+
+/// A network namespace.
+pub struct NetNamespace {
+    /// The interfaces in the namespace, where the first one is the loopback interface.
+    ifaces: RwLock<Vec<Arc<Iface>>>,
+    /// The user namespace that owns the namespace, where the capabilities are checked.
+    owner: Arc<UserNamespace>,
+}
+
+static INIT_NET_NS: Once<Arc<NetNamespace>> = Once::new();
+
+impl NetNamespace {
+    /// Returns the initial network namespace.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the network interfaces are not initialized.
+    pub fn get_init_singleton() -> &'static Arc<NetNamespace> {
+        INIT_NET_NS.get().unwrap()
+    }
+
+    /// Initializes the initial network namespace with the interfaces.
+    pub(super) fn init(ifaces: Vec<Arc<Iface>>) {
+        INIT_NET_NS.call_once(|| {
+            Arc::new(Self {
+                ifaces: RwLock::new(ifaces),
+                owner: UserNamespace::get_init_singleton().clone(),
+            })
+        });
+    }
+
+    /// Creates a new network namespace with a loopback interface, which is owned by the user
+    /// namespace.
+    pub fn new(owner: Arc<UserNamespace>) -> Arc<Self> {
+        let loopback = iface::new_loopback();
+        iface::spawn_background_poll_thread(loopback.clone());
+
+        Arc::new(Self {
+            ifaces: RwLock::new(vec![loopback]),
+            owner,
+        })
+    }
+
+    /// Returns the user namespace that owns the namespace.
+    pub fn owner(&self) -> &Arc<UserNamespace> {
+        &self.owner
+    }
+
+    /// Returns the interfaces in the namespace.
+    pub fn ifaces(&self) -> RwLockReadGuard<'_, Vec<Arc<Iface>>, PreemptDisabled> {
+        self.ifaces.read()
+    }
+
+    /// Returns the loopback interface.
+    pub fn loopback_iface(&self) -> Arc<Iface> {
+        self.ifaces.read()[0].clone()
+    }
+
+    /// Returns the interface that the packets to the non-local addresses are sent through.
+    ///
+    /// This is the first interface with an IPv4 address other than the loopback interface, or
+    /// the loopback interface if there is no such interface.
+    //
+    // FIXME: The interface should be chosen according to the routing table.
+    pub fn default_iface(&self) -> Arc<Iface> {
+        let ifaces = self.ifaces.read();
+        ifaces[1..]
+            .iter()
+            .find(|iface| iface.ipv4_addr().is_some())
+            .unwrap_or(&ifaces[0])
+            .clone()
+    }
+
+    /// Adds the interface to the namespace.
+    ///
+    /// # Errors
+    ///
+    /// This method will fail with [`Errno::EEXIST`] if there is already an interface with the
+    /// same name.
+    pub(super) fn add_iface(&self, iface: Arc<Iface>) -> Result<()> {
+        let mut ifaces = self.ifaces.write();
+        if ifaces.iter().any(|other| other.name() == iface.name()) {
+            return_errno_with_message!(Errno::EEXIST, "the interface name is already in use");
+        }
+        ifaces.push(iface);
+        Ok(())
+    }
+
+    /// Removes the interface with the index from the namespace.
+    pub(super) fn remove_iface(&self, index: u32) -> Option<Arc<Iface>> {
+        let mut ifaces = self.ifaces.write();
+        let pos = ifaces.iter().position(|iface| iface.index() == index)?;
+        Some(ifaces.remove(pos))
+    }
+
+    /// Finds the interface with the index.
+    pub fn find_iface_by_index(&self, index: u32) -> Option<Arc<Iface>> {
+        self.find_iface(|iface| iface.index() == index)
+    }
+
+    /// Finds the interface with the name.
+    pub fn find_iface_by_name(&self, name: &str) -> Option<Arc<Iface>> {
+        self.find_iface(|iface| iface.name() == name)
+    }
+
+    /// Finds the interface that has the IPv4 address.
+    pub fn find_iface_by_ipv4_addr(&self, ipv4_addr: Ipv4Address) -> Option<Arc<Iface>> {
+        self.find_iface(|iface| iface.ipv4_addr() == Some(ipv4_addr))
+    }
+
+    /// Finds the interface whose subnet contains the IPv4 address.
+    pub fn find_iface_by_subnet(&self, ipv4_addr: Ipv4Address) -> Option<Arc<Iface>> {
+        self.find_iface(|iface| {
+            iface
+                .ipv4_cidr()
+                .is_some_and(|cidr| cidr.contains_addr(&ipv4_addr))
+        })
+    }
+
+    fn find_iface(&self, mut predicate: impl FnMut(&Arc<Iface>) -> bool) -> Option<Arc<Iface>> {
+        self.ifaces
+            .read()
+            .iter()
+            .find(|iface| predicate(iface))
+            .cloned()
+    }
+}
+
+impl Drop for NetNamespace {
+    fn drop(&mut self) {
+        // The sockets hold the namespace, so no socket can use the interfaces any more.
+        for iface in self.ifaces.get_mut().iter() {
+            iface.sched_poll().stop();
+
+            // As in Linux, the virtual devices are destroyed with the namespace. Note that this
+            // also destroys the other end of a veth pair, which may be in another namespace.
+            if let Some(device) = VirtDevice::from_iface(iface) {
+                device.destroy();
+            }
+        }
+    }
+}
@@ -7,49 +7,42 @@ use aster_bigtcp::{
 };
 
 use crate::{
-    net::iface::{iter_all_ifaces, loopback_iface, virtio_iface, BoundPort, Iface},
+    net::{
+        iface::{BoundPort, Iface},
+        namespace::NetNamespace,
+        sysctl,
+    },
     prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
 };
 
-pub(super) fn get_iface_to_bind(ip_addr: &IpAddress) -> Option<Arc<Iface>> {
+pub(super) fn get_iface_to_bind(net_ns: &NetNamespace, ip_addr: &IpAddress) -> Option<Arc<Iface>> {
     let IpAddress::Ipv4(ipv4_addr) = ip_addr;
-    iter_all_ifaces()
-        .find(|iface| {
-            if let Some(iface_ipv4_addr) = iface.ipv4_addr() {
-                iface_ipv4_addr == *ipv4_addr
-            } else {
-                false
-            }
-        })
-        .map(Clone::clone)
+    net_ns.find_iface_by_ipv4_addr(*ipv4_addr)
 }
 
 /// Get a suitable iface to deal with sendto/connect request if the socket is not bound to an iface.
 /// If the remote address is the same as that of some iface, we will use the iface.
-/// Otherwise, we will use a default interface.
-fn get_ephemeral_iface(remote_ip_addr: &IpAddress) -> Arc<Iface> {
+/// If the remote address is in the subnet of some iface, we will use the iface.
+/// Otherwise, we will use the default interface of the network namespace.
+fn get_ephemeral_iface(net_ns: &NetNamespace, remote_ip_addr: &IpAddress) -> Arc<Iface> {
     let IpAddress::Ipv4(remote_ipv4_addr) = remote_ip_addr;
-    if let Some(iface) = iter_all_ifaces().find(|iface| {
-        if let Some(iface_ipv4_addr) = iface.ipv4_addr() {
-            iface_ipv4_addr == *remote_ipv4_addr
-        } else {
-            false
-        }
-    }) {
-        return iface.clone();
+    if let Some(iface) = net_ns.find_iface_by_ipv4_addr(*remote_ipv4_addr) {
+        return iface;
     }
-
-    // FIXME: Instead of hardcoding the rules here, we should choose the
-    // default interface according to the routing table.
-    if let Some(virtio_iface) = virtio_iface() {
-        virtio_iface.clone()
-    } else {
-        loopback_iface().clone()
+    if let Some(iface) = net_ns.find_iface_by_subnet(*remote_ipv4_addr) {
+        return iface;
     }
+
+    net_ns.default_iface()
 }
 
-pub(super) fn bind_port(endpoint: &IpEndpoint, can_reuse: bool) -> Result<BoundPort> {
-    let iface = match get_iface_to_bind(&endpoint.addr) {
+pub(super) fn bind_port(
+    net_ns: &NetNamespace,
+    endpoint: &IpEndpoint,
+    can_reuse: bool,
+) -> Result<BoundPort> {
+    let iface = match get_iface_to_bind(net_ns, &endpoint.addr) {
         Some(iface) => iface,
         None => {
             return_errno_with_message!(
@@ -59,11 +52,36 @@ pub(super) fn bind_port(endpoint: &IpEndpoint, can_reuse: bool) -> Result<BoundP
         }
     };
 
+    check_port_privilege(net_ns, endpoint.port)?;
+
     let bind_port_config = BindPortConfig::new(endpoint.port, can_reuse);
 
     Ok(iface.bind(bind_port_config)?)
 }
 
+/// Checks whether the current thread is allowed to bind the port.
+///
+/// Like Linux, binding a port below `net.ipv4.ip_unprivileged_port_start` requires
+/// `CAP_NET_BIND_SERVICE`. Port zero, which is used to request an ephemeral port, is always
+/// allowed.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/ipv4/af_inet.c>
+fn check_port_privilege(net_ns: &NetNamespace, port: u16) -> Result<()> {
+    if port == 0 || port >= sysctl::ip_unprivileged_port_start() {
+        return Ok(());
+    }
+
+    let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+    if !credentials.has_capability_in(CapSet::NET_BIND_SERVICE, net_ns.owner()) {
+        return_errno_with_message!(
+            Errno::EACCES,
+            "binding a privileged port requires `CAP_NET_BIND_SERVICE`"
+        );
+    }
+
+    Ok(())
+}
+
 impl From<BindError> for Error {
     fn from(value: BindError) -> Self {
         match value {
@@ -77,8 +95,11 @@ impl From<BindError> for Error {
     }
 }
 
-pub(super) fn get_ephemeral_endpoint(remote_endpoint: &IpEndpoint) -> IpEndpoint {
-    let iface = get_ephemeral_iface(&remote_endpoint.addr);
+pub(super) fn get_ephemeral_endpoint(
+    net_ns: &NetNamespace,
+    remote_endpoint: &IpEndpoint,
+) -> IpEndpoint {
+    let iface = get_ephemeral_iface(net_ns, &remote_endpoint.addr);
     let ip_addr = iface.ipv4_addr().unwrap();
     IpEndpoint::new(IpAddress::Ipv4(ip_addr), 0)
 }
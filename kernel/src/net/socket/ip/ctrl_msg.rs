@@ -0,0 +1,138 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use aster_bigtcp::wire::IpAddress;
+
+use crate::{
+    net::socket::util::{options::TimestampFormat, socket_addr::SocketAddr},
+    prelude::*,
+    time::{timespec_t, timeval_t},
+    util::net::{socket_addr_to_c_bytes_and, CSocketOptionLevel},
+};
+
+/// Control messages of IP sockets.
+#[derive(Debug)]
+pub enum IpControlMessage {
+    /// The time when the data were received, passed with `SCM_TIMESTAMP` or `SCM_TIMESTAMPNS`.
+    Timestamp(Duration, TimestampFormat),
+    /// The error in the error queue, passed with `IP_RECVERR`.
+    RecvErr(IpExtendedError),
+}
+
+/// The types of the socket-level control messages of IP sockets.
+///
+/// The definition is from <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/asm-generic/socket.h#L141>.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy)]
+#[expect(non_camel_case_types)]
+enum CSocketControlMessageType {
+    SCM_TIMESTAMP = 29,
+    SCM_TIMESTAMPNS = 35,
+}
+
+/// The types of the IP-level control messages of IP sockets.
+///
+/// The definition is from <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/in.h#L104>.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy)]
+#[expect(non_camel_case_types)]
+enum CIpControlMessageType {
+    IP_RECVERR = 11,
+}
+
+impl IpControlMessage {
+    /// Converts the control message to its payload with at most `max_len` bytes.
+    ///
+    /// This method returns the level and the type of the control message, the payload, and
+    /// whether the payload is truncated.
+    pub(in crate::net) fn write_to(
+        self,
+        max_len: usize,
+    ) -> (CSocketOptionLevel, i32, Vec<u8>, bool) {
+        let (level, type_, payload) = match self {
+            Self::Timestamp(time, TimestampFormat::Timeval) => (
+                CSocketOptionLevel::SOL_SOCKET,
+                CSocketControlMessageType::SCM_TIMESTAMP as i32,
+                timeval_t::from(time).as_bytes().to_vec(),
+            ),
+            Self::Timestamp(time, TimestampFormat::Timespec) => (
+                CSocketOptionLevel::SOL_SOCKET,
+                CSocketControlMessageType::SCM_TIMESTAMPNS as i32,
+                timespec_t::from(time).as_bytes().to_vec(),
+            ),
+            Self::RecvErr(error) => (
+                CSocketOptionLevel::SOL_IP,
+                CIpControlMessageType::IP_RECVERR as i32,
+                error.to_bytes(),
+            ),
+        };
+
+        // The payloads cannot be partially received.
+        if payload.len() > max_len {
+            (level, type_, Vec::new(), true)
+        } else {
+            (level, type_, payload, false)
+        }
+    }
+}
+
+/// An error reported by an ICMP message, i.e., `struct sock_extended_err` followed by the
+/// address of the offender in Linux.
+#[derive(Debug)]
+pub struct IpExtendedError {
+    error: CSockExtendedErr,
+    offender: IpAddress,
+}
+
+/// The extended error reported via `IP_RECVERR`.
+///
+/// The definition is from <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/errqueue.h#L8>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CSockExtendedErr {
+    ee_errno: u32,
+    ee_origin: u8,
+    ee_type: u8,
+    ee_code: u8,
+    ee_pad: u8,
+    ee_info: u32,
+    ee_data: u32,
+}
+
+/// The error is reported by an ICMP message, i.e., `SO_EE_ORIGIN_ICMP` in Linux.
+const SO_EE_ORIGIN_ICMP: u8 = 2;
+
+/// The ICMP type of "destination unreachable" messages.
+const ICMP_DEST_UNREACH: u8 = 3;
+/// The ICMP code of "port unreachable" messages.
+const ICMP_PORT_UNREACH: u8 = 3;
+
+impl IpExtendedError {
+    /// Creates the error that is reported by an ICMP port unreachable message.
+    pub(in crate::net) fn new_port_unreachable(offender: IpAddress) -> Self {
+        let error = CSockExtendedErr {
+            ee_errno: Errno::ECONNREFUSED as u32,
+            ee_origin: SO_EE_ORIGIN_ICMP,
+            ee_type: ICMP_DEST_UNREACH,
+            ee_code: ICMP_PORT_UNREACH,
+            ee_pad: 0,
+            ee_info: 0,
+            ee_data: 0,
+        };
+
+        Self { error, offender }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.error.as_bytes().to_vec();
+
+        // The port of the offender is always zero, like Linux.
+        let IpAddress::Ipv4(offender) = self.offender;
+        socket_addr_to_c_bytes_and(&SocketAddr::IPv4(offender, 0), |addr_bytes| {
+            bytes.extend_from_slice(addr_bytes)
+        });
+
+        bytes
+    }
+}
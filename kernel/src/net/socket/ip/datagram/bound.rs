@@ -1,7 +1,10 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::time::Duration;
+
 use aster_bigtcp::{
     errors::udp::{RecvError, SendError},
+    socket::{PacketFilter, UdpIcmpError},
     wire::IpEndpoint,
 };
 
@@ -31,37 +34,66 @@ impl BoundDatagram {
     pub(super) fn iface(&self) -> &Arc<Iface> {
         self.bound_socket.iface()
     }
-}
 
-impl datagram_common::Bound for BoundDatagram {
-    type Endpoint = IpEndpoint;
+    pub(super) fn set_can_reuse(&self, can_reuse: bool) {
+        self.bound_socket.set_can_reuse(can_reuse);
+    }
 
-    fn local_endpoint(&self) -> Self::Endpoint {
-        self.bound_socket.local_endpoint().unwrap()
+    pub(super) fn set_filter(&self, filter: Option<Arc<dyn PacketFilter>>) -> Result<()> {
+        let is_detaching = filter.is_none();
+        if self.bound_socket.set_filter(filter).is_none() && is_detaching {
+            return_errno_with_message!(Errno::ENOENT, "no filter is attached");
+        }
+
+        Ok(())
     }
 
-    fn remote_endpoint(&self) -> Option<&Self::Endpoint> {
-        self.remote_endpoint.as_ref()
+    pub(super) fn set_recv_err(&self, recv_err: bool) {
+        self.bound_socket.set_recv_err(recv_err);
     }
 
-    fn set_remote_endpoint(&mut self, endpoint: &Self::Endpoint) {
-        self.remote_endpoint = Some(*endpoint)
+    /// Takes the pending error of the socket, if any.
+    ///
+    /// The error is `ECONNREFUSED` if an ICMP port unreachable message has been received for the
+    /// connected remote endpoint. Like Linux, such errors are reported only for connected
+    /// sockets, and are discarded otherwise, unless `IP_RECVERR` is enabled.
+    pub(super) fn take_error(&self) -> Option<Error> {
+        let unreachable_endpoint = self.bound_socket.take_unreachable_endpoint()?;
+
+        (self.bound_socket.is_recv_err() || self.remote_endpoint == Some(unreachable_endpoint))
+            .then(|| Error::with_message(Errno::ECONNREFUSED, "the connection is refused"))
     }
 
-    fn try_recv(
+    fn has_error(&self) -> bool {
+        if self.bound_socket.has_queued_error() {
+            return true;
+        }
+
+        self.bound_socket
+            .unreachable_endpoint()
+            .is_some_and(|unreachable| {
+                self.bound_socket.is_recv_err() || self.remote_endpoint == Some(unreachable)
+            })
+    }
+
+    /// Receives a packet with the time when it was received.
+    pub(super) fn try_recv_with_time(
         &self,
         writer: &mut dyn MultiWrite,
-        _flags: SendRecvFlags,
-    ) -> Result<(usize, Self::Endpoint)> {
-        let result = self.bound_socket.recv(|packet, udp_metadata| {
+    ) -> Result<(usize, IpEndpoint, Duration)> {
+        if let Some(err) = self.take_error() {
+            return Err(err);
+        }
+
+        let result = self.bound_socket.recv(|packet, udp_metadata, time| {
             let copied_res = writer.write(&mut VmReader::from(packet));
             let endpoint = udp_metadata.endpoint;
-            (copied_res, endpoint)
+            (copied_res, endpoint, time)
         });
 
         match result {
-            Ok((Ok(res), endpoint)) => Ok((res, endpoint)),
-            Ok((Err(e), _)) => Err(e),
+            Ok((Ok(res), endpoint, time)) => Ok((res, endpoint, time)),
+            Ok((Err(e), _, _)) => Err(e),
             Err(RecvError::Exhausted) => {
                 return_errno_with_message!(Errno::EAGAIN, "the receive buffer is empty")
             }
@@ -71,12 +103,56 @@ impl datagram_common::Bound for BoundDatagram {
         }
     }
 
+    /// Receives an error from the error queue (i.e., `MSG_ERRQUEUE`).
+    ///
+    /// The payload of the packet that caused the error is written to `writer`.
+    pub(super) fn try_recv_error(
+        &self,
+        writer: &mut dyn MultiWrite,
+    ) -> Result<(usize, UdpIcmpError)> {
+        let Some(error) = self.bound_socket.take_queued_error() else {
+            return_errno_with_message!(Errno::EAGAIN, "the error queue is empty");
+        };
+
+        let copied_bytes = writer.write(&mut VmReader::from(error.payload.as_slice()))?;
+        Ok((copied_bytes, error))
+    }
+}
+
+impl datagram_common::Bound for BoundDatagram {
+    type Endpoint = IpEndpoint;
+
+    fn local_endpoint(&self) -> Self::Endpoint {
+        self.bound_socket.local_endpoint().unwrap()
+    }
+
+    fn remote_endpoint(&self) -> Option<&Self::Endpoint> {
+        self.remote_endpoint.as_ref()
+    }
+
+    fn set_remote_endpoint(&mut self, endpoint: &Self::Endpoint) {
+        self.remote_endpoint = Some(*endpoint)
+    }
+
+    fn try_recv(
+        &self,
+        writer: &mut dyn MultiWrite,
+        _flags: SendRecvFlags,
+    ) -> Result<(usize, Self::Endpoint)> {
+        self.try_recv_with_time(writer)
+            .map(|(recv_bytes, endpoint, _)| (recv_bytes, endpoint))
+    }
+
     fn try_send(
         &self,
         reader: &mut dyn MultiRead,
         remote: &Self::Endpoint,
         _flags: SendRecvFlags,
     ) -> Result<usize> {
+        if let Some(err) = self.take_error() {
+            return Err(err);
+        }
+
         let result = self
             .bound_socket
             .send(reader.sum_lens(), *remote, |socket_buffer| {
@@ -115,6 +191,10 @@ impl datagram_common::Bound for BoundDatagram {
                 events |= IoEvents::OUT;
             }
 
+            if self.has_error() {
+                events |= IoEvents::ERR;
+            }
+
             events
         })
     }
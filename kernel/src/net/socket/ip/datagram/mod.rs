@@ -1,26 +1,36 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
 
-use aster_bigtcp::wire::IpEndpoint;
+use aster_bigtcp::{socket::PacketFilter, wire::IpEndpoint};
 use unbound::BindOptions;
 
 use self::{bound::BoundDatagram, unbound::UnboundDatagram};
-use super::UNSPECIFIED_LOCAL_ENDPOINT;
+use super::{
+    ctrl_msg::{IpControlMessage, IpExtendedError},
+    options::{IpOptionSet, RecvErr, SetIpLevelOption},
+    UNSPECIFIED_LOCAL_ENDPOINT,
+};
 use crate::{
     events::IoEvents,
-    match_sock_option_mut,
-    net::socket::{
-        options::{Error as SocketError, SocketOption},
-        private::SocketPrivate,
-        util::{
-            datagram_common::{select_remote_and_bind, Bound, Inner},
-            options::{SetSocketLevelOption, SocketOptionSet},
-            send_recv_flags::SendRecvFlags,
-            socket_addr::SocketAddr,
-            MessageHeader,
+    match_sock_option_mut, match_sock_option_ref,
+    net::{
+        namespace::NetNamespace,
+        socket::{
+            options::{AttachBpf, DetachBpf, Error as SocketError, SocketOption},
+            private::SocketPrivate,
+            util::{
+                datagram_common::{select_remote_and_bind, Bound, Inner},
+                options::{SetSocketLevelOption, SocketOptionSet},
+                send_recv_flags::SendRecvFlags,
+                socket_addr::SocketAddr,
+                ControlMessage, MessageHeader,
+            },
+            Socket,
         },
-        Socket,
     },
     prelude::*,
     process::signal::{PollHandle, Pollable, Pollee},
@@ -36,13 +46,15 @@ pub(in crate::net) use self::observer::DatagramObserver;
 #[derive(Debug, Clone)]
 struct OptionSet {
     socket: SocketOptionSet,
+    ip: IpOptionSet,
     // TODO: UDP option set
 }
 
 impl OptionSet {
     fn new() -> Self {
         let socket = SocketOptionSet::new_udp();
-        OptionSet { socket }
+        let ip = IpOptionSet::new_udp();
+        OptionSet { socket, ip }
     }
 }
 
@@ -52,33 +64,67 @@ pub struct DatagramSocket {
     options: RwLock<OptionSet>,
 
     is_nonblocking: AtomicBool,
+    is_async: AtomicBool,
     pollee: Pollee,
 }
 
 impl DatagramSocket {
-    pub fn new(is_nonblocking: bool) -> Arc<Self> {
-        let unbound_datagram = UnboundDatagram::new();
+    /// Creates a socket in the network namespace.
+    pub fn new(is_nonblocking: bool, net_ns: Arc<NetNamespace>) -> Arc<Self> {
+        let unbound_datagram = UnboundDatagram::new(net_ns);
         Arc::new(Self {
             inner: RwMutex::new(Inner::Unbound(unbound_datagram)),
             options: RwLock::new(OptionSet::new()),
             is_nonblocking: AtomicBool::new(is_nonblocking),
+            is_async: AtomicBool::new(false),
             pollee: Pollee::new(),
         })
     }
 
-    fn try_recv(
-        &self,
-        writer: &mut dyn MultiWrite,
-        flags: SendRecvFlags,
-    ) -> Result<(usize, SocketAddr)> {
-        let recv_bytes = self
-            .inner
-            .read()
-            .try_recv(writer, flags)
-            .map(|(recv_bytes, remote_endpoint)| (recv_bytes, remote_endpoint.into()))?;
+    fn try_recv(&self, writer: &mut dyn MultiWrite) -> Result<(usize, SocketAddr, Duration)> {
+        let (recv_bytes, remote_endpoint, time) = match &*self.inner.read() {
+            Inner::Unbound(_) => {
+                return_errno_with_message!(Errno::EAGAIN, "the socket is not bound");
+            }
+            Inner::Bound(bound_datagram) => bound_datagram.try_recv_with_time(writer)?,
+        };
+        self.pollee.invalidate();
+
+        Ok((recv_bytes, remote_endpoint.into(), time))
+    }
+
+    /// Receives an error from the error queue (i.e., `MSG_ERRQUEUE`).
+    ///
+    /// Like Linux, this never blocks, even if the error queue is empty.
+    fn try_recv_error(&self, writer: &mut dyn MultiWrite) -> Result<(usize, MessageHeader)> {
+        let (recv_bytes, error) = match &*self.inner.read() {
+            Inner::Unbound(_) => {
+                return_errno_with_message!(Errno::EAGAIN, "the error queue is empty");
+            }
+            Inner::Bound(bound_datagram) => bound_datagram.try_recv_error(writer)?,
+        };
         self.pollee.invalidate();
 
-        Ok(recv_bytes)
+        let mut control_messages = Vec::new();
+        if let Some(format) = self.options.read().socket.timestamp() {
+            control_messages.push(ControlMessage::Ip(IpControlMessage::Timestamp(
+                error.time, format,
+            )));
+        }
+        control_messages.push(ControlMessage::Ip(IpControlMessage::RecvErr(
+            IpExtendedError::new_port_unreachable(error.offender),
+        )));
+
+        let mut flags = SendRecvFlags::MSG_ERRQUEUE;
+        if recv_bytes < error.payload.len() {
+            flags |= SendRecvFlags::MSG_TRUNC;
+        }
+
+        let message_header =
+            MessageHeader::new(Some(error.remote_endpoint.into()), control_messages)
+                .with_flags(flags);
+
+        Ok((recv_bytes, message_header))
     }
 
     fn try_send(
@@ -113,6 +159,20 @@ impl DatagramSocket {
 
         Ok(sent_bytes)
     }
+
+    fn set_filter(&self, filter: Option<Arc<dyn PacketFilter>>) -> Result<()> {
+        match &mut *self.inner.write() {
+            Inner::Unbound(unbound_datagram) => unbound_datagram.set_filter(filter),
+            Inner::Bound(bound_datagram) => bound_datagram.set_filter(filter),
+        }
+    }
+
+    fn set_recv_err(&self, recv_err: bool) {
+        match &mut *self.inner.write() {
+            Inner::Unbound(unbound_datagram) => unbound_datagram.set_recv_err(recv_err),
+            Inner::Bound(bound_datagram) => bound_datagram.set_recv_err(recv_err),
+        }
+    }
 }
 
 impl Pollable for DatagramSocket {
@@ -130,6 +190,19 @@ impl SocketPrivate for DatagramSocket {
     fn set_nonblocking(&self, is_nonblocking: bool) {
         self.is_nonblocking.store(is_nonblocking, Ordering::Relaxed);
     }
+
+    fn is_async(&self) -> bool {
+        self.is_async.load(Ordering::Relaxed)
+    }
+
+    fn set_async(&self, is_async: bool) {
+        self.is_async.store(is_async, Ordering::Relaxed);
+    }
+
+    fn busy_poll_timeout(&self) -> Duration {
+        let busy_poll = self.options.read().socket.busy_poll();
+        Duration::from_micros(busy_poll as u64)
+    }
 }
 
 impl Socket for DatagramSocket {
@@ -174,13 +247,17 @@ impl Socket for DatagramSocket {
         flags: SendRecvFlags,
     ) -> Result<usize> {
         // TODO: Deal with flags
+        if flags.contains(SendRecvFlags::MSG_OOB) {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "MSG_OOB is not supported");
+        }
         if !flags.is_all_supported() {
             warn!("unsupported flags: {:?}", flags);
         }
 
         let MessageHeader {
             addr,
-            control_message,
+            control_messages,
+            ..
         } = message_header;
 
         let endpoint = match addr {
@@ -188,7 +265,7 @@ impl Socket for DatagramSocket {
             None => None,
         };
 
-        if control_message.is_some() {
+        if !control_messages.is_empty() {
             // TODO: Support sending control message
             warn!("sending control message is not supported");
         }
@@ -203,16 +280,25 @@ impl Socket for DatagramSocket {
         flags: SendRecvFlags,
     ) -> Result<(usize, MessageHeader)> {
         // TODO: Deal with flags
-        if !flags.is_all_supported() {
+        if !(flags - SendRecvFlags::MSG_ERRQUEUE).is_all_supported() {
             warn!("unsupported flags: {:?}", flags);
         }
 
-        let (received_bytes, peer_addr) =
-            self.block_on(IoEvents::IN, || self.try_recv(writer, flags))?;
+        if flags.contains(SendRecvFlags::MSG_ERRQUEUE) {
+            return self.try_recv_error(writer);
+        }
+
+        let (received_bytes, peer_addr, time) =
+            self.block_on(IoEvents::IN, || self.try_recv(writer))?;
 
-        // TODO: Receive control message
+        let mut control_messages = Vec::new();
+        if let Some(format) = self.options.read().socket.timestamp() {
+            control_messages.push(ControlMessage::Ip(IpControlMessage::Timestamp(
+                time, format,
+            )));
+        }
 
-        let message_header = MessageHeader::new(Some(peer_addr), None);
+        let message_header = MessageHeader::new(Some(peer_addr), control_messages);
 
         Ok((received_bytes, message_header))
     }
@@ -220,21 +306,56 @@ impl Socket for DatagramSocket {
     fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
         match_sock_option_mut!(option, {
             socket_errors: SocketError => {
-                // TODO: Support socket errors for UDP sockets
-                socket_errors.set(None);
+                let error = match &*self.inner.read() {
+                    Inner::Unbound(_) => None,
+                    Inner::Bound(bound_datagram) => bound_datagram.take_error(),
+                };
+                socket_errors.set(error);
                 return Ok(());
             },
             _ => ()
         });
 
-        self.options.read().socket.get_option(option)
+        let options = self.options.read();
+
+        // Deal with socket-level options
+        match options.socket.get_option(option) {
+            Err(err) if err.error() == Errno::ENOPROTOOPT => (),
+            res => return res,
+        }
+
+        // Deal with IP-level options
+        options.ip.get_option(option)
     }
 
     fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        match_sock_option_ref!(option, {
+            attach_bpf: AttachBpf => {
+                let filter = attach_bpf.get().unwrap().clone();
+                return self.set_filter(Some(filter));
+            },
+            _detach_bpf: DetachBpf => {
+                return self.set_filter(None);
+            },
+            ip_recv_err: RecvErr => {
+                let recv_err = *ip_recv_err.get().unwrap();
+                self.set_recv_err(recv_err);
+                self.options.write().ip.set_recv_err(recv_err);
+                return Ok(());
+            },
+            _ => ()
+        });
+
         let inner = self.inner.read();
         let mut options = self.options.write();
 
-        match options.socket.set_option(option, &*inner) {
+        let res = match options.socket.set_option(option, &*inner) {
+            // Deal with IP-level options
+            Err(err) if err.error() == Errno::ENOPROTOOPT => options.ip.set_option(option, &*inner),
+            res => res,
+        };
+
+        match res {
             Err(e) => Err(e),
             Ok(need_iface_poll) => {
                 let iface_to_poll = need_iface_poll
@@ -257,4 +378,20 @@ impl Socket for DatagramSocket {
     }
 }
 
-impl SetSocketLevelOption for Inner<UnboundDatagram, BoundDatagram> {}
+impl SetSocketLevelOption for Inner<UnboundDatagram, BoundDatagram> {
+    fn set_reuse_addr(&self, reuse_addr: bool) {
+        // Unbound sockets will use the new option when they are bound.
+        if let Inner::Bound(bound_datagram) = self {
+            bound_datagram.set_can_reuse(reuse_addr);
+        }
+    }
+}
+
+impl SetIpLevelOption for Inner<UnboundDatagram, BoundDatagram> {
+    fn set_hdrincl(&self, _hdrincl: bool) -> Result<()> {
+        return_errno_with_message!(
+            Errno::ENOPROTOOPT,
+            "IP_HDRINCL cannot be set on UDP sockets"
+        );
+    }
+}
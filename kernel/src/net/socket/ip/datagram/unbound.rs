@@ -1,25 +1,53 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use aster_bigtcp::{socket::UdpSocket, wire::IpEndpoint};
+use aster_bigtcp::{
+    socket::{PacketFilter, UdpSocket},
+    wire::IpEndpoint,
+};
 
 use super::{bound::BoundDatagram, DatagramObserver};
 use crate::{
     events::IoEvents,
-    net::socket::{
-        ip::common::{bind_port, get_ephemeral_endpoint},
-        util::datagram_common,
+    net::{
+        namespace::NetNamespace,
+        socket::{
+            ip::common::{bind_port, get_ephemeral_endpoint},
+            util::datagram_common,
+        },
     },
     prelude::*,
     process::signal::Pollee,
 };
 
 pub(super) struct UnboundDatagram {
-    _private: (),
+    /// The network namespace where the socket will be bound.
+    net_ns: Arc<NetNamespace>,
+    /// The filter of the incoming packets, which will be used when the socket is bound.
+    filter: Option<Arc<dyn PacketFilter>>,
+    /// Whether the ICMP errors are queued (i.e., `IP_RECVERR`) when the socket is bound.
+    is_recv_err: bool,
 }
 
 impl UnboundDatagram {
-    pub(super) fn new() -> Self {
-        Self { _private: () }
+    pub(super) fn new(net_ns: Arc<NetNamespace>) -> Self {
+        Self {
+            net_ns,
+            filter: None,
+            is_recv_err: false,
+        }
+    }
+
+    pub(super) fn set_filter(&mut self, filter: Option<Arc<dyn PacketFilter>>) -> Result<()> {
+        if filter.is_none() && self.filter.is_none() {
+            return_errno_with_message!(Errno::ENOENT, "no filter is attached");
+        }
+
+        self.filter = filter;
+        Ok(())
+    }
+
+    pub(super) fn set_recv_err(&mut self, is_recv_err: bool) {
+        self.is_recv_err = is_recv_err;
     }
 }
 
@@ -39,7 +67,7 @@ impl datagram_common::Unbound for UnboundDatagram {
         pollee: &Pollee,
         options: BindOptions,
     ) -> Result<Self::Bound> {
-        let bound_port = bind_port(endpoint, options.can_reuse)?;
+        let bound_port = bind_port(&self.net_ns, endpoint, options.can_reuse)?;
 
         let bound_socket =
             match UdpSocket::new_bind(bound_port, DatagramObserver::new(pollee.clone())) {
@@ -48,6 +76,8 @@ impl datagram_common::Unbound for UnboundDatagram {
                     unreachable!("`new_bind` fails with {:?}, which should not happen", err)
                 }
             };
+        bound_socket.set_filter(self.filter.clone());
+        bound_socket.set_recv_err(self.is_recv_err);
 
         Ok(BoundDatagram::new(bound_socket))
     }
@@ -57,7 +87,7 @@ impl datagram_common::Unbound for UnboundDatagram {
         remote_endpoint: &Self::Endpoint,
         pollee: &Pollee,
     ) -> Result<Self::Bound> {
-        let endpoint = get_ephemeral_endpoint(remote_endpoint);
+        let endpoint = get_ephemeral_endpoint(&self.net_ns, remote_endpoint);
         self.bind(&endpoint, pollee, BindOptions { can_reuse: false })
     }
 
@@ -2,8 +2,10 @@
 
 mod addr;
 mod common;
+mod ctrl_msg;
 pub mod datagram;
 pub mod options;
 pub mod stream;
 
 use addr::UNSPECIFIED_LOCAL_ENDPOINT;
+pub use ctrl_msg::IpControlMessage;
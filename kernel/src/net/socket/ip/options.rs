@@ -17,6 +17,7 @@ pub(super) struct IpOptionSet {
     tos: u8,
     ttl: IpTtl,
     hdrincl: bool,
+    recv_err: bool,
 }
 
 const DEFAULT_TTL: u8 = 64;
@@ -28,6 +29,16 @@ impl IpOptionSet {
             tos: 0,
             ttl: IpTtl(None),
             hdrincl: false,
+            recv_err: false,
+        }
+    }
+
+    pub(super) const fn new_udp() -> Self {
+        Self {
+            tos: 0,
+            ttl: IpTtl(None),
+            hdrincl: false,
+            recv_err: false,
         }
     }
 
@@ -45,6 +56,10 @@ impl IpOptionSet {
                 let hdrincl = self.hdrincl();
                 ip_hdrincl.set(hdrincl);
             },
+            ip_recv_err: RecvErr => {
+                let recv_err = self.recv_err();
+                ip_recv_err.set(recv_err);
+            },
             _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option is unknown")
         });
 
@@ -73,6 +88,10 @@ impl IpOptionSet {
                 socket.set_hdrincl(*hdrincl)?;
                 self.set_hdrincl(*hdrincl);
             },
+            ip_recv_err: RecvErr => {
+                let recv_err = ip_recv_err.get().unwrap();
+                self.set_recv_err(*recv_err);
+            },
             _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to be set is unknown")
         });
 
@@ -84,6 +103,7 @@ impl_socket_options!(
     pub struct Tos(i32);
     pub struct Ttl(IpTtl);
     pub struct Hdrincl(bool);
+    pub struct RecvErr(bool);
 );
 
 #[derive(Debug, Clone, Copy)]
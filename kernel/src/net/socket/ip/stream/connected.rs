@@ -1,7 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use aster_bigtcp::{
-    errors::tcp::{RecvError, SendError},
+    errors::tcp::{RecvError, RecvUrgentError, SendError},
     socket::{NeedIfacePoll, RawTcpSetOption},
     wire::IpEndpoint,
 };
@@ -18,6 +18,7 @@ use crate::{
     },
     prelude::*,
     process::signal::{Pollee, Poller},
+    thread::Thread,
     util::{MultiRead, MultiWrite},
 };
 
@@ -105,17 +106,51 @@ impl ConnectedStream {
         }
     }
 
+    /// Receives the urgent data (i.e., `MSG_OOB`).
+    ///
+    /// Like Linux, this method never blocks.
+    pub fn try_recv_urgent(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<usize> {
+        let is_peek = flags.contains(SendRecvFlags::MSG_PEEK);
+
+        match self.tcp_conn.recv_urgent(is_peek) {
+            Ok(byte) => writer.write(&mut VmReader::from([byte].as_slice())),
+            Err(RecvUrgentError::NotArrived)
+                if self
+                    .tcp_conn
+                    .raw_with(|socket| socket.is_recv_shut() || !socket.may_recv_new()) =>
+            {
+                Ok(0)
+            }
+            Err(RecvUrgentError::NotArrived) => {
+                return_errno_with_message!(Errno::EAGAIN, "the urgent data has not arrived")
+            }
+            Err(RecvUrgentError::NoData) => {
+                return_errno_with_message!(Errno::EINVAL, "there is no urgent data to receive")
+            }
+        }
+    }
+
     pub fn try_send(
         &self,
         reader: &mut dyn MultiRead,
-        _flags: SendRecvFlags,
+        flags: SendRecvFlags,
     ) -> Result<(usize, NeedIfacePoll)> {
-        let result = self.tcp_conn.send(|socket_buffer| {
-            match reader.read(&mut VmWriter::from(socket_buffer)) {
+        let copy_data =
+            |socket_buffer: &mut [u8]| match reader.read(&mut VmWriter::from(socket_buffer)) {
                 Ok(len) => (len, Ok(len)),
                 Err(e) => (0, Err(e)),
-            }
-        });
+            };
+
+        // The last byte is sent as the urgent data if `MSG_OOB` is specified.
+        let result = if flags.contains(SendRecvFlags::MSG_OOB) {
+            self.tcp_conn.send_urgent(copy_data)
+        } else {
+            self.tcp_conn.send(copy_data)
+        };
 
         match result {
             Ok((Ok(0), need_poll)) => {
@@ -192,6 +227,11 @@ impl ConnectedStream {
                 events |= IoEvents::ERR;
             }
 
+            // If there is urgent data to receive, add a PRI event.
+            if socket.has_urgent_data() {
+                events |= IoEvents::PRI;
+            }
+
             events
         })
     }
@@ -207,6 +247,10 @@ impl ConnectedStream {
         }
     }
 
+    pub(super) fn set_can_reuse(&self, can_reuse: bool) {
+        self.tcp_conn.set_can_reuse(can_reuse);
+    }
+
     pub(super) fn set_raw_option<R>(
         &self,
         set_option: impl FnOnce(&dyn RawTcpSetOption) -> R,
@@ -214,6 +258,10 @@ impl ConnectedStream {
         set_option(&self.tcp_conn)
     }
 
+    pub(super) fn is_at_urgent_mark(&self) -> bool {
+        self.tcp_conn.raw_with(|socket| socket.is_at_urgent_mark())
+    }
+
     pub(super) fn raw_with<R>(&self, f: impl FnOnce(&RawTcpSocketExt) -> R) -> R {
         self.tcp_conn.raw_with(f)
     }
@@ -245,6 +293,11 @@ pub(super) fn close_and_linger(tcp_conn: TcpConnection, linger: LingerOption, po
         }
     };
 
+    // Like Linux, the socket does not linger if it is closed because the thread is exiting.
+    if Thread::current().is_none_or(|thread| thread.is_exited()) {
+        return;
+    }
+
     let mut poller = Poller::new(Some(&timeout));
     pollee.register_poller(poller.as_handle_mut(), IoEvents::HUP);
 
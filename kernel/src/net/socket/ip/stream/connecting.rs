@@ -16,6 +16,8 @@ use crate::{
 pub struct ConnectingStream {
     tcp_conn: TcpConnection,
     remote_endpoint: IpEndpoint,
+    /// Indicates whether the connecting phase is aborted by `shutdown()`.
+    is_aborted: bool,
 }
 
 pub enum ConnResult {
@@ -61,9 +63,20 @@ impl ConnectingStream {
         Ok(Self {
             tcp_conn,
             remote_endpoint,
+            is_aborted: false,
         })
     }
 
+    /// Aborts the connecting phase.
+    ///
+    /// Like Linux, the connection will fail with `ECONNRESET` instead of `ECONNREFUSED`.
+    ///
+    /// Polling the iface is _always_ required after this method succeeds.
+    pub fn shutdown(&mut self) {
+        self.tcp_conn.reset();
+        self.is_aborted = true;
+    }
+
     pub fn has_result(&self) -> bool {
         match self.tcp_conn.connect_state() {
             ConnectState::Connecting => false,
@@ -82,9 +95,17 @@ impl ConnectingStream {
                 self.remote_endpoint,
                 true,
             )),
-            ConnectState::Refused => ConnResult::Refused(InitStream::new_refused(
-                self.tcp_conn.into_bound_port().unwrap(),
-            )),
+            ConnectState::Refused => {
+                let error = if self.is_aborted {
+                    Errno::ECONNRESET
+                } else {
+                    Errno::ECONNREFUSED
+                };
+                ConnResult::Refused(InitStream::new_failed(
+                    self.tcp_conn.into_bound_port().unwrap(),
+                    error,
+                ))
+            }
         }
     }
 
@@ -104,6 +125,10 @@ impl ConnectingStream {
         IoEvents::empty()
     }
 
+    pub(super) fn set_can_reuse(&self, can_reuse: bool) {
+        self.tcp_conn.set_can_reuse(can_reuse);
+    }
+
     pub(super) fn set_raw_option<R>(
         &self,
         set_option: impl FnOnce(&dyn RawTcpSetOption) -> R,
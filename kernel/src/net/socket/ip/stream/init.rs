@@ -12,6 +12,7 @@ use crate::{
     events::IoEvents,
     net::{
         iface::BoundPort,
+        namespace::NetNamespace,
         socket::{
             ip::common::{bind_port, get_ephemeral_endpoint},
             SocketAddr,
@@ -36,12 +37,17 @@ pub struct InitStream {
     ///    `connect()`, which checks and resets the boolean value and returns an appropriate error
     ///    code.
     is_connect_done: bool,
-    /// Indicates whether the socket error is `ECONNREFUSED`.
+    /// Indicates whether the socket error is `conn_error`.
     ///
-    /// This boolean value is set to true when the connection is refused and set to false when the
+    /// This boolean value is set to true when the connection fails and set to false when the
     /// error code is reported via `getsockopt(SOL_SOCKET, SO_ERROR)`, `send()`, `recv()`, or
     /// `connect()`.
-    is_conn_refused: AtomicBool,
+    has_conn_error: AtomicBool,
+    /// The error code of the last connection failure.
+    ///
+    /// This is `ECONNREFUSED` if the connection is refused, or `ECONNRESET` if the connecting
+    /// phase is aborted by `shutdown()`.
+    conn_error: Errno,
 }
 
 impl InitStream {
@@ -49,7 +55,8 @@ impl InitStream {
         Self {
             bound_port: None,
             is_connect_done: true,
-            is_conn_refused: AtomicBool::new(false),
+            has_conn_error: AtomicBool::new(false),
+            conn_error: Errno::ECONNREFUSED,
         }
     }
 
@@ -57,30 +64,38 @@ impl InitStream {
         Self {
             bound_port: Some(bound_port),
             is_connect_done: true,
-            is_conn_refused: AtomicBool::new(false),
+            has_conn_error: AtomicBool::new(false),
+            conn_error: Errno::ECONNREFUSED,
         }
     }
 
-    pub fn new_refused(bound_port: BoundPort) -> Self {
+    pub fn new_failed(bound_port: BoundPort, conn_error: Errno) -> Self {
         Self {
             bound_port: Some(bound_port),
             is_connect_done: false,
-            is_conn_refused: AtomicBool::new(true),
+            has_conn_error: AtomicBool::new(true),
+            conn_error,
         }
     }
 
-    pub fn bind(&mut self, endpoint: &IpEndpoint, can_reuse: bool) -> Result<()> {
+    pub fn bind(
+        &mut self,
+        net_ns: &NetNamespace,
+        endpoint: &IpEndpoint,
+        can_reuse: bool,
+    ) -> Result<()> {
         if self.bound_port.is_some() {
             return_errno_with_message!(Errno::EINVAL, "the socket is already bound to an address");
         }
 
-        self.bound_port = Some(bind_port(endpoint, can_reuse)?);
+        self.bound_port = Some(bind_port(net_ns, endpoint, can_reuse)?);
 
         Ok(())
     }
 
     pub fn connect(
         self,
+        net_ns: &NetNamespace,
         remote_endpoint: &IpEndpoint,
         option: &RawTcpOption,
         observer: StreamObserver,
@@ -93,8 +108,8 @@ impl InitStream {
         let bound_port = if let Some(bound_port) = self.bound_port {
             bound_port
         } else {
-            let endpoint = get_ephemeral_endpoint(remote_endpoint);
-            match bind_port(&endpoint, false) {
+            let endpoint = get_ephemeral_endpoint(net_ns, remote_endpoint);
+            match bind_port(net_ns, &endpoint, false) {
                 Ok(bound_port) => bound_port,
                 Err(err) => return Err((err, self)),
             }
@@ -103,7 +118,7 @@ impl InitStream {
         ConnectingStream::new(bound_port, *remote_endpoint, option, observer).map_err(
             |(err, bound_port)| {
                 if err.error() == Errno::ECONNREFUSED {
-                    (err, InitStream::new_refused(bound_port))
+                    (err, InitStream::new_failed(bound_port, Errno::ECONNREFUSED))
                 } else {
                     (err, InitStream::new_bound(bound_port))
                 }
@@ -118,10 +133,10 @@ impl InitStream {
 
         self.is_connect_done = true;
 
-        let is_conn_refused = self.is_conn_refused.get_mut();
-        if *is_conn_refused {
-            *is_conn_refused = false;
-            return_errno_with_message!(Errno::ECONNREFUSED, "the connection is refused");
+        let has_conn_error = self.has_conn_error.get_mut();
+        if *has_conn_error {
+            *has_conn_error = false;
+            return Err(self.new_conn_error());
         } else {
             return_errno_with_message!(
                 Errno::ECONNABORTED,
@@ -197,11 +212,17 @@ impl InitStream {
             .map(|bound_port| bound_port.endpoint().unwrap())
     }
 
+    pub(super) fn set_can_reuse(&self, can_reuse: bool) {
+        if let Some(bound_port) = self.bound_port.as_ref() {
+            bound_port.set_can_reuse(can_reuse);
+        }
+    }
+
     pub(super) fn check_io_events(&self) -> IoEvents {
         // Linux adds OUT and HUP events for a newly created socket
         let mut events = IoEvents::OUT | IoEvents::HUP;
 
-        if self.is_conn_refused.load(Ordering::Relaxed) {
+        if self.has_conn_error.load(Ordering::Relaxed) {
             events |= IoEvents::ERR;
         }
 
@@ -209,13 +230,18 @@ impl InitStream {
     }
 
     pub(super) fn test_and_clear_error(&self) -> Option<Error> {
-        if self.is_conn_refused.swap(false, Ordering::Relaxed) {
-            Some(Error::with_message(
-                Errno::ECONNREFUSED,
-                "the connection is refused",
-            ))
+        if self.has_conn_error.swap(false, Ordering::Relaxed) {
+            Some(self.new_conn_error())
         } else {
             None
         }
     }
+
+    fn new_conn_error(&self) -> Error {
+        if self.conn_error == Errno::ECONNRESET {
+            Error::with_message(Errno::ECONNRESET, "the connecting phase is aborted")
+        } else {
+            Error::with_message(self.conn_error, "the connection is refused")
+        }
+    }
 }
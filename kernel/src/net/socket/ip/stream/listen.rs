@@ -9,7 +9,10 @@ use aster_bigtcp::{
 use super::{connected::ConnectedStream, StreamObserver};
 use crate::{
     events::IoEvents,
-    net::iface::{BoundPort, Iface, TcpListener},
+    net::{
+        iface::{BoundPort, Iface, TcpListener},
+        sysctl,
+    },
     prelude::*,
 };
 
@@ -24,8 +27,7 @@ impl ListenStream {
         option: &RawTcpOption,
         observer: StreamObserver,
     ) -> core::result::Result<Self, (BoundPort, Error)> {
-        const SOMAXCONN: usize = 4096;
-        let max_conn = SOMAXCONN.min(backlog);
+        let max_conn = max_conn_of(backlog);
 
         match TcpListener::new_listen(bound_port, max_conn, option, observer) {
             Ok(tcp_listener) => Ok(Self { tcp_listener }),
@@ -39,6 +41,11 @@ impl ListenStream {
         }
     }
 
+    /// Updates the backlog when `listen` is called again on the listening socket.
+    pub(super) fn set_backlog(&self, backlog: usize) {
+        self.tcp_listener.set_max_conn(max_conn_of(backlog));
+    }
+
     pub fn try_accept(&self) -> Result<ConnectedStream> {
         let (new_conn, remote_endpoint) = self.tcp_listener.accept().ok_or_else(|| {
             Error::with_message(Errno::EAGAIN, "no pending connection is available")
@@ -67,6 +74,10 @@ impl ListenStream {
         }
     }
 
+    pub(super) fn set_can_reuse(&self, can_reuse: bool) {
+        self.tcp_listener.set_can_reuse(can_reuse);
+    }
+
     pub(super) fn set_raw_option<R>(
         &self,
         set_option: impl FnOnce(&dyn RawTcpSetOption) -> R,
@@ -74,7 +85,20 @@ impl ListenStream {
         set_option(&self.tcp_listener)
     }
 
+    /// Stops listening, but keeps the socket bound to the port.
+    ///
+    /// Polling the iface is _always_ required after this method succeeds.
+    pub(super) fn into_bound_port(self) -> BoundPort {
+        self.tcp_listener.close_and_keep_port()
+    }
+
     pub(super) fn into_listener(self) -> TcpListener {
         self.tcp_listener
     }
 }
+
+/// Returns the maximum number of pending connections for the backlog, which is limited by
+/// `net.core.somaxconn`.
+fn max_conn_of(backlog: usize) -> usize {
+    backlog.min(sysctl::somaxconn())
+}
@@ -4,6 +4,7 @@ use core::sync::atomic::{AtomicBool, Ordering};
 
 use aster_bigtcp::{
     socket::{NeedIfacePoll, RawTcpOption, RawTcpSetOption},
+    time::Duration,
     wire::IpEndpoint,
 };
 use connected::{close_and_linger, ConnectedStream};
@@ -11,8 +12,8 @@ use connecting::{ConnResult, ConnectingStream};
 use init::InitStream;
 use listen::ListenStream;
 use options::{
-    Congestion, DeferAccept, Inq, KeepIdle, MaxSegment, NoDelay, SynCnt, UserTimeout, WindowClamp,
-    KEEPALIVE_INTERVAL,
+    Congestion, DeferAccept, FastOpen, Inq, KeepCnt, KeepIdle, KeepIntvl, MaxSegment, NoDelay,
+    SynCnt, UserTimeout, WindowClamp,
 };
 use ostd::sync::{PreemptDisabled, RwLockReadGuard, RwLockWriteGuard};
 use takeable::Takeable;
@@ -24,22 +25,25 @@ use super::{
 };
 use crate::{
     events::IoEvents,
-    fs::file_handle::FileLike,
+    fs::{file_handle::FileLike, utils::IoctlCmd},
     match_sock_option_mut, match_sock_option_ref,
     net::{
         iface::Iface,
+        namespace::NetNamespace,
         socket::{
             options::{Error as SocketError, SocketOption},
             private::SocketPrivate,
             util::{
                 options::{SetSocketLevelOption, SocketOptionSet},
                 send_recv_flags::SendRecvFlags,
+                send_sigpipe_on_epipe,
                 shutdown_cmd::SockShutdownCmd,
                 socket_addr::SocketAddr,
                 MessageHeader,
             },
             Socket,
         },
+        sysctl::{self, TcpFastOpenMode},
     },
     prelude::*,
     process::signal::{PollHandle, Pollable, Pollee},
@@ -63,7 +67,9 @@ pub struct StreamSocket {
     options: RwLock<OptionSet>,
 
     is_nonblocking: AtomicBool,
+    is_async: AtomicBool,
     pollee: Pollee,
+    net_ns: Arc<NetNamespace>,
 }
 
 enum State {
@@ -93,36 +99,52 @@ impl OptionSet {
     }
 
     fn raw(&self) -> RawTcpOption {
+        let (keep_alive, timeout) = self.raw_keep_alive().unzip();
         RawTcpOption {
-            keep_alive: self.socket.keep_alive().then_some(KEEPALIVE_INTERVAL),
+            keep_alive,
+            timeout,
             is_nagle_enabled: !self.tcp.no_delay(),
+            is_urgent_inline: self.socket.oob_inline(),
         }
     }
+
+    /// Returns the keepalive interval and the timeout of the raw socket, if keepalive is enabled.
+    fn raw_keep_alive(&self) -> Option<(Duration, Duration)> {
+        self.socket.keep_alive().then(|| self.tcp.raw_keep_alive())
+    }
 }
 
 impl StreamSocket {
-    pub fn new(is_nonblocking: bool) -> Arc<Self> {
+    /// Creates a socket in the network namespace.
+    pub fn new(is_nonblocking: bool, net_ns: Arc<NetNamespace>) -> Arc<Self> {
         let init_stream = InitStream::new();
         Arc::new(Self {
             state: RwLock::new(Takeable::new(State::Init(init_stream))),
             options: RwLock::new(OptionSet::new()),
             is_nonblocking: AtomicBool::new(is_nonblocking),
+            is_async: AtomicBool::new(false),
             pollee: Pollee::new(),
+            net_ns,
         })
     }
 
-    fn new_accepted(connected_stream: ConnectedStream) -> Arc<Self> {
+    fn new_accepted(connected_stream: ConnectedStream, net_ns: Arc<NetNamespace>) -> Arc<Self> {
         let options = connected_stream.raw_with(|raw_tcp_socket| {
             let mut options = OptionSet::new();
 
-            if raw_tcp_socket.keep_alive().is_some() {
+            if let Some(interval) = raw_tcp_socket.keep_alive() {
                 options.socket.set_keep_alive(true);
+                options.tcp.set_keep_idle(interval.secs() as u32);
             }
 
             if !raw_tcp_socket.nagle_enabled() {
                 options.tcp.set_no_delay(true);
             }
 
+            if raw_tcp_socket.is_urgent_inline() {
+                options.socket.set_oob_inline(true);
+            }
+
             // TODO: Update other options for a newly-accepted socket
 
             options
@@ -135,7 +157,9 @@ impl StreamSocket {
             options: RwLock::new(options),
             state: RwLock::new(Takeable::new(State::Connected(connected_stream))),
             is_nonblocking: AtomicBool::new(false),
+            is_async: AtomicBool::new(false),
             pollee,
+            net_ns,
         })
     }
 
@@ -236,6 +260,7 @@ impl StreamSocket {
             }
 
             let (target_state, iface_to_poll) = match init_stream.connect(
+                &self.net_ns,
                 remote_endpoint,
                 &raw_option,
                 StreamObserver::new(self.pollee.clone()),
@@ -290,6 +315,22 @@ impl StreamSocket {
         }
     }
 
+    /// Connects to the remote endpoint for `MSG_FASTOPEN`.
+    ///
+    /// Data in SYN packets are not supported yet, so the data are sent after the normal three-way
+    /// handshake, which is also what Linux does when no valid TFO cookie is available.
+    fn connect_for_fastopen(&self, remote: Option<SocketAddr>) -> Result<()> {
+        if !sysctl::tcp_fastopen_mode().contains(TcpFastOpenMode::CLIENT_ENABLE) {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "TCP Fast Open is disabled");
+        }
+
+        let Some(remote) = remote else {
+            return_errno_with_message!(Errno::EINVAL, "the destination address is not specified");
+        };
+
+        self.connect(remote)
+    }
+
     fn try_accept(&self) -> Result<(Arc<dyn FileLike>, SocketAddr)> {
         let state = self.read_updated_state();
 
@@ -299,7 +340,7 @@ impl StreamSocket {
 
         let accepted = listen_stream.try_accept().map(|connected_stream| {
             let remote_endpoint = connected_stream.remote_endpoint();
-            let accepted_socket = Self::new_accepted(connected_stream);
+            let accepted_socket = Self::new_accepted(connected_stream, self.net_ns.clone());
             (accepted_socket as _, remote_endpoint.into())
         });
         let iface_to_poll = listen_stream.iface().clone();
@@ -349,6 +390,37 @@ impl StreamSocket {
         Ok((recv_bytes, remote_endpoint.into()))
     }
 
+    fn try_recv_urgent(&self, writer: &mut dyn MultiWrite, flags: SendRecvFlags) -> Result<usize> {
+        let state = self.read_updated_state();
+
+        let connected_stream = match state.as_ref() {
+            State::Connected(connected_stream) => connected_stream,
+            State::Listen(_) => {
+                return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected")
+            }
+            State::Init(_) | State::Connecting(_) => {
+                return_errno_with_message!(Errno::EINVAL, "there is no urgent data to receive")
+            }
+        };
+
+        let result = connected_stream.try_recv_urgent(writer, flags);
+        self.pollee.invalidate();
+
+        result
+    }
+
+    fn is_at_urgent_mark(&self) -> Result<bool> {
+        let state = self.read_updated_state();
+
+        match state.as_ref() {
+            State::Connected(connected_stream) => Ok(connected_stream.is_at_urgent_mark()),
+            State::Listen(_) => {
+                return_errno_with_message!(Errno::EINVAL, "the socket is listening")
+            }
+            State::Init(_) | State::Connecting(_) => Ok(false),
+        }
+    }
+
     fn try_send(&self, reader: &mut dyn MultiRead, flags: SendRecvFlags) -> Result<usize> {
         let state = self.read_updated_state();
 
@@ -422,6 +494,19 @@ impl SocketPrivate for StreamSocket {
     fn set_nonblocking(&self, nonblocking: bool) {
         self.is_nonblocking.store(nonblocking, Ordering::Relaxed);
     }
+
+    fn is_async(&self) -> bool {
+        self.is_async.load(Ordering::Relaxed)
+    }
+
+    fn set_async(&self, is_async: bool) {
+        self.is_async.store(is_async, Ordering::Relaxed);
+    }
+
+    fn busy_poll_timeout(&self) -> core::time::Duration {
+        let busy_poll = self.options.read().socket.busy_poll();
+        core::time::Duration::from_micros(busy_poll as u64)
+    }
 }
 
 impl Socket for StreamSocket {
@@ -434,7 +519,7 @@ impl Socket for StreamSocket {
         };
 
         let can_reuse = self.options.read().socket.reuse_addr();
-        init_stream.bind(&endpoint, can_reuse)
+        init_stream.bind(&self.net_ns, &endpoint, can_reuse)
     }
 
     fn connect(&self, socket_addr: SocketAddr) -> Result<()> {
@@ -457,6 +542,7 @@ impl Socket for StreamSocket {
             let init_stream = match owned_state {
                 State::Init(init_stream) => init_stream,
                 State::Listen(listen_stream) => {
+                    listen_stream.set_backlog(backlog);
                     return (State::Listen(listen_stream), Ok(()));
                 }
                 State::Connecting(_) | State::Connected(_) => {
@@ -487,24 +573,47 @@ impl Socket for StreamSocket {
     }
 
     fn accept(&self) -> Result<(Arc<dyn FileLike>, SocketAddr)> {
-        self.block_on(IoEvents::IN, || self.try_accept())
+        self.block_on_exclusive(IoEvents::IN, || self.try_accept())
     }
 
     fn shutdown(&self, cmd: SockShutdownCmd) -> Result<()> {
-        let state = self.read_updated_state();
+        let mut state = self.write_updated_state();
 
-        let (result, iface_to_poll) = match state.as_ref() {
+        let (result, iface_to_poll) = match state.as_mut() {
             State::Connected(connected_stream) => (
                 connected_stream.shutdown(cmd, &self.pollee),
                 connected_stream.iface().clone(),
             ),
-            // TODO: shutdown listening stream
-            _ => return_errno_with_message!(Errno::EINVAL, "cannot shutdown"),
+            // Linux does nothing if only the sending half of a listening socket is shut down.
+            State::Listen(_) if !cmd.shut_read() => return Ok(()),
+            State::Listen(listen_stream) => {
+                let iface_to_poll = listen_stream.iface().clone();
+                // The socket stops listening, but it is still bound to the port, so it can listen
+                // again. Pending and future `accept()` calls will fail.
+                state.borrow(|owned_state| {
+                    let State::Listen(listen_stream) = owned_state else {
+                        unreachable!("`State::Listen` is checked before calling `borrow`");
+                    };
+                    State::Init(InitStream::new_bound(listen_stream.into_bound_port()))
+                });
+                self.pollee
+                    .notify(IoEvents::IN | IoEvents::OUT | IoEvents::HUP);
+                (Ok(()), iface_to_poll)
+            }
+            State::Init(_) => {
+                return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected")
+            }
+            // Like Linux, the connecting phase is aborted regardless of the shutdown command, and
+            // the socket will become unconnected after the connection is reset.
+            State::Connecting(connecting_stream) => {
+                connecting_stream.shutdown();
+                (Ok(()), connecting_stream.iface().clone())
+            }
         };
 
         drop(state);
         // No need to call `Pollee::invalidate` because `ConnectedStream::shutdown` will call
-        // `Pollee::notify`.
+        // `Pollee::notify`, and a connecting stream will be notified when it is reset.
         iface_to_poll.poll();
 
         result
@@ -542,26 +651,32 @@ impl Socket for StreamSocket {
         flags: SendRecvFlags,
     ) -> Result<usize> {
         // TODO: Deal with flags
-        if !flags.is_all_supported() {
+        if !(flags - SendRecvFlags::MSG_FASTOPEN - SendRecvFlags::MSG_OOB).is_all_supported() {
             warn!("unsupported flags: {:?}", flags);
         }
 
         let MessageHeader {
-            control_message, ..
+            addr,
+            control_messages,
+            ..
         } = message_header;
 
+        if flags.contains(SendRecvFlags::MSG_FASTOPEN) {
+            self.connect_for_fastopen(addr)?;
+        }
+
         // According to the Linux man pages, `EISCONN` _may_ be returned when the destination
         // address is specified for a connection-mode socket. In practice, the destination address
         // is simply ignored. We follow the same behavior as the Linux implementation to ignore it.
 
-        if control_message.is_some() {
+        if !control_messages.is_empty() {
             // TODO: Support sending control message
             warn!("sending control message is not supported");
         }
 
-        self.block_on(IoEvents::OUT, || self.try_send(reader, flags))
-
-        // TODO: Trigger `SIGPIPE` if the error code is `EPIPE` and `MSG_NOSIGNAL` is not specified
+        let res = self.block_on(IoEvents::OUT, || self.try_send(reader, flags));
+        send_sigpipe_on_epipe(&res, flags);
+        res
     }
 
     fn recvmsg(
@@ -570,17 +685,22 @@ impl Socket for StreamSocket {
         flags: SendRecvFlags,
     ) -> Result<(usize, MessageHeader)> {
         // TODO: Deal with flags
-        if !flags.is_all_supported() {
+        if !(flags - SendRecvFlags::MSG_OOB).is_all_supported() {
             warn!("unsupported flags: {:?}", flags);
         }
 
-        let (received_bytes, _) = self.block_on(IoEvents::IN, || self.try_recv(writer, flags))?;
+        let received_bytes = if flags.contains(SendRecvFlags::MSG_OOB) {
+            self.try_recv_urgent(writer, flags)?
+        } else {
+            self.block_on(IoEvents::IN, || self.try_recv(writer, flags))?
+                .0
+        };
 
         // TODO: Receive control message
 
         // According to <https://elixir.bootlin.com/linux/v6.0.9/source/net/ipv4/tcp.c#L2645>,
         // peer address is ignored for connected socket.
-        let message_header = MessageHeader::new(None, None);
+        let message_header = MessageHeader::new(None, Vec::new());
 
         Ok((received_bytes, message_header))
     }
@@ -635,6 +755,14 @@ impl Socket for StreamSocket {
                 let keep_idle = options.tcp.keep_idle();
                 tcp_keep_idle.set(keep_idle);
             },
+            tcp_keep_intvl: KeepIntvl => {
+                let keep_intvl = options.tcp.keep_intvl();
+                tcp_keep_intvl.set(keep_intvl);
+            },
+            tcp_keep_cnt: KeepCnt => {
+                let keep_cnt = options.tcp.keep_cnt();
+                tcp_keep_cnt.set(keep_cnt);
+            },
             tcp_syn_cnt: SynCnt => {
                 let syn_cnt = options.tcp.syn_cnt();
                 tcp_syn_cnt.set(syn_cnt);
@@ -656,6 +784,10 @@ impl Socket for StreamSocket {
                 let user_timeout = options.tcp.user_timeout();
                 tcp_user_timeout.set(user_timeout);
             },
+            tcp_fastopen: FastOpen => {
+                let fastopen_qlen = options.tcp.fastopen_qlen();
+                tcp_fastopen.set(fastopen_qlen);
+            },
             tcp_inq: Inq => {
                 let inq = options.tcp.receive_inq();
                 tcp_inq.set(inq);
@@ -671,7 +803,12 @@ impl Socket for StreamSocket {
         let mut options = self.options.write();
 
         // Deal with socket-level options
-        let need_iface_poll = match options.socket.set_option(option, state.as_mut()) {
+        let tcp_options = options.tcp;
+        let socket_level = StateWithTcpOptions {
+            state: state.as_ref(),
+            tcp: &tcp_options,
+        };
+        let need_iface_poll = match options.socket.set_option(option, &socket_level) {
             Err(err) if err.error() == Errno::ENOPROTOOPT => {
                 // Deal with IP-level options
                 match options.ip.set_option(option, state.as_mut()) {
@@ -698,6 +835,17 @@ impl Socket for StreamSocket {
 
         Ok(())
     }
+
+    fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
+        match cmd {
+            IoctlCmd::SIOCATMARK => {
+                let is_at_mark = self.is_at_urgent_mark()? as i32;
+                current_userspace!().write_val(arg, &is_at_mark)?;
+                Ok(0)
+            }
+            _ => return_errno_with_message!(Errno::EINVAL, "the ioctl command is not supported"),
+        }
+    }
 }
 
 fn do_tcp_setsockopt(
@@ -731,7 +879,31 @@ fn do_tcp_setsockopt(
             }
             options.tcp.set_keep_idle(*keepidle);
 
-            // TODO: Track when the socket becomes idle to actually support keep idle.
+            return Ok(state.set_raw_keep_alive(options.raw_keep_alive()));
+        },
+        tcp_keep_intvl: KeepIntvl => {
+            const MIN_KEEP_INTVL: u32 = 1;
+            const MAX_KEEP_INTVL: u32 = 32767;
+
+            let keepintvl = tcp_keep_intvl.get().unwrap();
+            if *keepintvl < MIN_KEEP_INTVL || *keepintvl > MAX_KEEP_INTVL {
+                return_errno_with_message!(Errno::EINVAL, "the keepalive interval is out of bounds");
+            }
+            options.tcp.set_keep_intvl(*keepintvl);
+
+            return Ok(state.set_raw_keep_alive(options.raw_keep_alive()));
+        },
+        tcp_keep_cnt: KeepCnt => {
+            const MIN_KEEP_CNT: u32 = 1;
+            const MAX_KEEP_CNT: u32 = 127;
+
+            let keepcnt = tcp_keep_cnt.get().unwrap();
+            if *keepcnt < MIN_KEEP_CNT || *keepcnt > MAX_KEEP_CNT {
+                return_errno_with_message!(Errno::EINVAL, "the keepalive count is out of bounds");
+            }
+            options.tcp.set_keep_cnt(*keepcnt);
+
+            return Ok(state.set_raw_keep_alive(options.raw_keep_alive()));
         },
         tcp_syn_cnt: SynCnt => {
             const MAX_TCP_SYN_CNT: u8 = 127;
@@ -770,6 +942,15 @@ fn do_tcp_setsockopt(
             }
             options.tcp.set_user_timeout(*user_timeout);
         },
+        tcp_fastopen: FastOpen => {
+            // Data in SYN packets are not supported yet, so the connections always fall back to
+            // the normal three-way handshake, as if the TFO cookies of the clients were invalid.
+            let fastopen_qlen = tcp_fastopen.get().unwrap();
+            if (*fastopen_qlen as i32) < 0 || !matches!(state, State::Init(_) | State::Listen(_)) {
+                return_errno_with_message!(Errno::EINVAL, "TCP Fast Open cannot be enabled");
+            }
+            options.tcp.set_fastopen_qlen(*fastopen_qlen);
+        },
         tcp_inq: Inq => {
             let inq = tcp_inq.get().unwrap();
             options.tcp.set_receive_inq(*inq);
@@ -796,6 +977,33 @@ impl State {
         }
     }
 
+    /// Sets the keepalive interval and the timeout of the raw socket.
+    fn set_raw_keep_alive(&self, keep_alive: Option<(Duration, Duration)>) -> NeedIfacePoll {
+        let (interval, timeout) = keep_alive.unzip();
+
+        let set_keep_alive = |raw_socket: &dyn RawTcpSetOption| {
+            let need_poll_for_interval = raw_socket.set_keep_alive(interval);
+            let need_poll_for_timeout = raw_socket.set_timeout(timeout);
+            if *need_poll_for_interval || *need_poll_for_timeout {
+                NeedIfacePoll::TRUE
+            } else {
+                NeedIfacePoll::FALSE
+            }
+        };
+
+        self.set_raw_option(set_keep_alive)
+            .unwrap_or(NeedIfacePoll::FALSE)
+    }
+
+    fn set_can_reuse(&self, can_reuse: bool) {
+        match self {
+            State::Init(init_stream) => init_stream.set_can_reuse(can_reuse),
+            State::Connecting(connecting_stream) => connecting_stream.set_can_reuse(can_reuse),
+            State::Connected(connected_stream) => connected_stream.set_can_reuse(can_reuse),
+            State::Listen(listen_stream) => listen_stream.set_can_reuse(can_reuse),
+        }
+    }
+
     fn iface(&self) -> Option<&Arc<Iface>> {
         match self {
             State::Init(_) => None,
@@ -806,18 +1014,27 @@ impl State {
     }
 }
 
-impl SetSocketLevelOption for State {
-    fn set_keep_alive(&self, keep_alive: bool) -> NeedIfacePoll {
-        let interval = if keep_alive {
-            Some(KEEPALIVE_INTERVAL)
-        } else {
-            None
-        };
+/// The socket state with the TCP-level options, which are needed to set some socket-level options.
+struct StateWithTcpOptions<'a> {
+    state: &'a State,
+    tcp: &'a TcpOptionSet,
+}
 
-        let set_keepalive = |raw_socket: &dyn RawTcpSetOption| raw_socket.set_keep_alive(interval);
+impl SetSocketLevelOption for StateWithTcpOptions<'_> {
+    fn set_reuse_addr(&self, reuse_addr: bool) {
+        self.state.set_can_reuse(reuse_addr);
+    }
 
-        self.set_raw_option(set_keepalive)
-            .unwrap_or(NeedIfacePoll::FALSE)
+    fn set_keep_alive(&self, keep_alive: bool) -> NeedIfacePoll {
+        let raw_keep_alive = keep_alive.then(|| self.tcp.raw_keep_alive());
+        self.state.set_raw_keep_alive(raw_keep_alive)
+    }
+
+    fn set_oob_inline(&self, oob_inline: bool) {
+        self.state
+            .set_raw_option(|raw_socket: &dyn RawTcpSetOption| {
+                raw_socket.set_urgent_inline(oob_inline)
+            });
     }
 }
 
@@ -39,6 +39,10 @@ impl SocketEventObserver for StreamObserver {
             io_events |= IoEvents::HUP | IoEvents::ERR;
         }
 
+        if events.contains(SocketEvents::URGENT) {
+            io_events |= IoEvents::PRI;
+        }
+
         self.0.notify(io_events);
     }
 }
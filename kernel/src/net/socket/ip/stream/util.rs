@@ -11,16 +11,22 @@ pub struct TcpOptionSet {
     no_delay: bool,
     maxseg: u32,
     keep_idle: u32,
+    keep_intvl: u32,
+    keep_cnt: u32,
     syn_cnt: u8,
     defer_accept: Retrans,
     window_clamp: u32,
     congestion: CongestionControl,
     user_timeout: u32,
     receive_inq: bool,
+    /// The maximum length of the queue of the TCP Fast Open requests (i.e., `TCP_FASTOPEN`).
+    fastopen_qlen: u32,
 }
 
 pub const DEFAULT_MAXSEG: u32 = 536;
 pub const DEFAULT_KEEP_IDLE: u32 = 7200;
+pub const DEFAULT_KEEP_INTVL: u32 = 75;
+pub const DEFAULT_KEEP_CNT: u32 = 9;
 pub const DEFAULT_SYN_CNT: u8 = 6;
 pub const DEFAULT_WINDOW_CLAMP: u32 = 0x8000_0000;
 
@@ -30,16 +36,39 @@ impl TcpOptionSet {
             no_delay: false,
             maxseg: DEFAULT_MAXSEG,
             keep_idle: DEFAULT_KEEP_IDLE,
+            keep_intvl: DEFAULT_KEEP_INTVL,
+            keep_cnt: DEFAULT_KEEP_CNT,
             syn_cnt: DEFAULT_SYN_CNT,
             defer_accept: Retrans(0),
             window_clamp: DEFAULT_WINDOW_CLAMP,
             congestion: CongestionControl::Reno,
             user_timeout: 0,
             receive_inq: false,
+            fastopen_qlen: 0,
         }
     }
 }
 
+impl TcpOptionSet {
+    /// Returns the keepalive interval and the timeout of the raw socket.
+    ///
+    /// The raw socket sends a keepalive probe whenever the connection has been idle for the
+    /// interval, and aborts the connection if nothing is received from the peer within the
+    /// timeout, which is the time for all the probes to go unanswered.
+    //
+    // FIXME: Linux sends the first probe after `TCP_KEEPIDLE` seconds and the subsequent probes
+    // every `TCP_KEEPINTVL` seconds. The raw socket can only send probes at a fixed interval, so
+    // the subsequent probes are sent every `TCP_KEEPIDLE` seconds instead.
+    pub fn raw_keep_alive(&self) -> (Duration, Duration) {
+        let idle = self.keep_idle as u64;
+        let probes = self.keep_intvl as u64 * self.keep_cnt as u64;
+        (
+            Duration::from_secs(idle),
+            Duration::from_secs(idle + probes),
+        )
+    }
+}
+
 impl Default for TcpOptionSet {
     fn default() -> Self {
         Self::new()
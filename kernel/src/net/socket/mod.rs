@@ -3,12 +3,12 @@
 use self::options::SocketOption;
 pub use self::util::{
     options::LingerOption, send_recv_flags::SendRecvFlags, shutdown_cmd::SockShutdownCmd,
-    socket_addr::SocketAddr, MessageHeader,
+    socket_addr::SocketAddr, ControlMessage, MessageHeader,
 };
 use crate::{
     fs::{
         file_handle::FileLike,
-        utils::{InodeMode, Metadata, StatusFlags},
+        utils::{InodeMode, IoctlCmd, Metadata, StatusFlags},
     },
     prelude::*,
     util::{MultiRead, MultiWrite},
@@ -22,7 +22,14 @@ mod util;
 pub mod vsock;
 
 mod private {
-    use crate::{events::IoEvents, prelude::*, process::signal::Pollable};
+    use core::time::Duration;
+
+    use crate::{
+        events::IoEvents,
+        prelude::*,
+        process::{posix_thread::AsPosixThread, signal::Pollable},
+        time::{clocks::MonotonicClock, Clock},
+    };
 
     /// Common methods for sockets, but private to the network module.
     ///
@@ -35,14 +42,60 @@ mod private {
         /// Sets whether the socket is in non-blocking mode.
         fn set_nonblocking(&self, nonblocking: bool);
 
+        /// Returns whether the socket is in asynchronous mode (i.e., `O_ASYNC`).
+        ///
+        /// In asynchronous mode, `SIGIO` is sent to the owner of the socket when I/O becomes
+        /// possible.
+        fn is_async(&self) -> bool;
+
+        /// Sets whether the socket is in asynchronous mode.
+        fn set_async(&self, is_async: bool);
+
+        /// Returns how long to busy poll before blocking for incoming data.
+        ///
+        /// This is set by the `SO_BUSY_POLL` socket option. Sockets that do not support the
+        /// option never busy poll.
+        fn busy_poll_timeout(&self) -> Duration {
+            Duration::ZERO
+        }
+
         /// Blocks until some events occur to complete I/O operations.
         ///
         /// If the socket is in non-blocking mode and the I/O operations cannot be completed
         /// immediately, this method will fail with [`EAGAIN`] instead of blocking.
         ///
+        /// If `events` contains [`IoEvents::IN`] and [`Self::busy_poll_timeout`] is not zero, the
+        /// I/O operations will be retried in a busy loop for that long before going to sleep.
+        ///
         /// [`EAGAIN`]: crate::error::Errno::EAGAIN
         #[track_caller]
         fn block_on<F, R>(&self, events: IoEvents, mut try_op: F) -> Result<R>
+        where
+            Self: Sized,
+            F: FnMut() -> Result<R>,
+        {
+            if self.is_nonblocking() {
+                return try_op();
+            }
+
+            let busy_poll_timeout = self.busy_poll_timeout();
+            if events.contains(IoEvents::IN) && !busy_poll_timeout.is_zero() {
+                match busy_poll(busy_poll_timeout, &mut try_op) {
+                    Err(err) if err.error() == Errno::EAGAIN => (),
+                    result => return result,
+                }
+            }
+
+            self.wait_events(events, None, try_op)
+        }
+
+        /// Blocks exclusively until some events occur to complete I/O operations.
+        ///
+        /// This method is the same as [`Self::block_on`], except that only one of the threads
+        /// blocking exclusively on the same socket is woken up when the events occur. It is
+        /// intended for operations like `accept()`, where an event can satisfy only one thread.
+        #[track_caller]
+        fn block_on_exclusive<F, R>(&self, events: IoEvents, mut try_op: F) -> Result<R>
         where
             Self: Sized,
             F: FnMut() -> Result<R>,
@@ -50,10 +103,43 @@ mod private {
             if self.is_nonblocking() {
                 try_op()
             } else {
-                self.wait_events(events, None, try_op)
+                self.wait_events_exclusive(events, None, try_op)
             }
         }
     }
+
+    /// Retries `try_op` in a busy loop until it stops failing with [`EAGAIN`] or `timeout`
+    /// expires.
+    ///
+    /// The loop also stops early if the current thread has pending signals, which will be
+    /// handled when the caller goes to sleep.
+    ///
+    /// [`EAGAIN`]: crate::error::Errno::EAGAIN
+    fn busy_poll<F, R>(timeout: Duration, try_op: &mut F) -> Result<R>
+    where
+        F: FnMut() -> Result<R>,
+    {
+        let clock = MonotonicClock::get();
+        let deadline = clock.read_time() + timeout;
+
+        let current_thread = current_thread!();
+        let posix_thread = current_thread.as_posix_thread();
+
+        loop {
+            match try_op() {
+                Err(err) if err.error() == Errno::EAGAIN => (),
+                result => return result,
+            }
+
+            if clock.read_time() >= deadline
+                || posix_thread.is_some_and(|posix_thread| posix_thread.has_pending())
+            {
+                return_errno_with_message!(Errno::EAGAIN, "the busy poll time is used up");
+            }
+
+            core::hint::spin_loop();
+        }
+    }
 }
 
 /// Operations defined on a socket.
@@ -123,6 +209,11 @@ pub trait Socket: private::SocketPrivate + Send + Sync {
         writers: &mut dyn MultiWrite,
         flags: SendRecvFlags,
     ) -> Result<(usize, MessageHeader)>;
+
+    /// Performs socket-specific I/O control operations (e.g., `SIOCATMARK`).
+    fn ioctl(&self, _cmd: IoctlCmd, _arg: usize) -> Result<i32> {
+        return_errno_with_message!(Errno::EINVAL, "the ioctl command is not supported");
+    }
 }
 
 impl<T: Socket + 'static> FileLike for T {
@@ -136,29 +227,36 @@ impl<T: Socket + 'static> FileLike for T {
         // TODO: Set correct flags
         self.sendmsg(
             reader,
-            MessageHeader {
-                addr: None,
-                control_message: None,
-            },
+            MessageHeader::new(None, Vec::new()),
             SendRecvFlags::empty(),
         )
     }
 
+    fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
+        Socket::ioctl(self, cmd, arg)
+    }
+
     fn status_flags(&self) -> StatusFlags {
-        // TODO: Support other flags (e.g., `O_ASYNC`)
-        if self.is_nonblocking() {
-            StatusFlags::O_NONBLOCK
-        } else {
-            StatusFlags::empty()
-        }
+        // TODO: Support other flags (e.g., `O_APPEND`)
+        let mut flags = StatusFlags::empty();
+        flags.set(StatusFlags::O_NONBLOCK, self.is_nonblocking());
+        flags.set(StatusFlags::O_ASYNC, self.is_async());
+        flags
     }
 
     fn set_status_flags(&self, new_flags: StatusFlags) -> Result<()> {
-        // TODO: Support other flags (e.g., `O_ASYNC`)
-        if new_flags.contains(StatusFlags::O_NONBLOCK) {
-            self.set_nonblocking(true);
-        } else {
-            self.set_nonblocking(false);
+        // TODO: Support other flags (e.g., `O_APPEND`)
+        self.set_nonblocking(new_flags.contains(StatusFlags::O_NONBLOCK));
+        self.set_async(new_flags.contains(StatusFlags::O_ASYNC));
+        Ok(())
+    }
+
+    fn update_status_flags(&self, mask: StatusFlags, new_flags: StatusFlags) -> Result<()> {
+        if mask.contains(StatusFlags::O_NONBLOCK) {
+            self.set_nonblocking(new_flags.contains(StatusFlags::O_NONBLOCK));
+        }
+        if mask.contains(StatusFlags::O_ASYNC) {
+            self.set_async(new_flags.contains(StatusFlags::O_ASYNC));
         }
         Ok(())
     }
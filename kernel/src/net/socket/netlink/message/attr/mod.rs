@@ -54,6 +54,22 @@ impl CAttrHeader {
     pub fn type_(&self) -> u16 {
         self.type_ & ATTRIBUTE_TYPE_MASK
     }
+
+    /// Returns the payload length (excluding padding).
+    pub fn payload_len(&self) -> Result<usize> {
+        (self.len as usize)
+            .checked_sub(size_of::<Self>())
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the attribute length is too small"))
+    }
+
+    /// Reads the payload as raw bytes from the `reader`.
+    pub fn read_payload(&self, reader: &mut dyn MultiRead) -> Result<Vec<u8>> {
+        let mut payload = vec![0u8; self.payload_len()?];
+        if reader.read(&mut VmWriter::from(payload.as_mut_slice()))? != payload.len() {
+            return_errno_with_message!(Errno::EINVAL, "the attribute payload is truncated");
+        }
+        Ok(payload)
+    }
 }
 
 const IS_NESTED_MASK: u16 = 1u16 << 15;
@@ -104,7 +120,9 @@ pub trait Attribute: Debug + Send + Sync {
 
         while total_len > 0 {
             let attr = Self::read_from(reader)?;
-            total_len -= attr.total_len();
+            total_len = total_len.checked_sub(attr.total_len()).ok_or_else(|| {
+                Error::with_message(Errno::EINVAL, "the attribute exceeds the total length")
+            })?;
 
             let padding_len = attr.padding_len().min(total_len);
             reader.skip(padding_len);
@@ -132,3 +150,49 @@ pub trait Attribute: Debug + Send + Sync {
         Ok(())
     }
 }
+
+/// Attributes that are nested in the payload of another attribute.
+#[derive(Debug, Clone)]
+pub struct NestedAttrs<A> {
+    attrs: Vec<A>,
+    /// The byte representation of the attributes, which is the payload of the outer attribute.
+    bytes: Vec<u8>,
+}
+
+impl<A: Attribute> NestedAttrs<A> {
+    /// Creates the nested attributes.
+    pub fn new(attrs: Vec<A>) -> Self {
+        let len = attrs.iter().map(|attr| attr.total_len_with_padding()).sum();
+
+        let mut bytes = vec![0u8; len];
+        let mut writer = VmWriter::from(bytes.as_mut_slice()).to_fallible();
+        for attr in attrs.iter() {
+            attr.write_to(&mut writer).unwrap();
+        }
+
+        Self { attrs, bytes }
+    }
+
+    /// Reads the nested attributes from the payload of the outer attribute.
+    pub fn read_from(header: &CAttrHeader, reader: &mut dyn MultiRead) -> Result<Self> {
+        Self::parse(header.read_payload(reader)?)
+    }
+
+    /// Parses the nested attributes from their byte representation.
+    pub fn parse(bytes: Vec<u8>) -> Result<Self> {
+        let attrs = A::read_all_from(
+            &mut VmReader::from(bytes.as_slice()).to_fallible(),
+            bytes.len(),
+        )?;
+
+        Ok(Self { attrs, bytes })
+    }
+
+    pub fn attrs(&self) -> &[A] {
+        &self.attrs
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
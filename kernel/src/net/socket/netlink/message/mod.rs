@@ -8,11 +8,11 @@
 mod attr;
 mod segment;
 
-pub(super) use attr::{noattr::NoAttr, Attribute, CAttrHeader};
+pub(super) use attr::{noattr::NoAttr, Attribute, CAttrHeader, NestedAttrs};
 pub(super) use segment::{
     ack::{DoneSegment, ErrorSegment},
     common::SegmentCommon,
-    header::{CMsgSegHdr, GetRequestFlags, SegHdrCommonFlags},
+    header::{CMsgSegHdr, GetRequestFlags, NewRequestFlags, SegHdrCommonFlags},
     CSegmentType, SegmentBody,
 };
 
@@ -25,7 +25,7 @@ use crate::{
 ///
 /// A netlink message can be transmitted to and from user space using a single send/receive syscall.
 /// It consists of one or more [`ProtocolSegment`]s.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(super) struct Message<T: ProtocolSegment> {
     segments: Vec<T>,
 }
@@ -45,7 +45,3 @@ mod table;
 pub use addr::{GroupIdSet, NetlinkSocketAddr};
 pub use route::NetlinkRouteSocket;
 pub use table::{is_valid_protocol, StandardNetlinkProtocol};
-
-pub(in crate::net) fn init() {
-    table::init();
-}
@@ -5,30 +5,42 @@ use core::ops::Sub;
 use super::message::RtnlMessage;
 use crate::{
     events::IoEvents,
-    net::socket::{
-        netlink::{
-            message::ProtocolSegment, route::kernel::get_netlink_route_kernel, table::BoundHandle,
-            NetlinkSocketAddr,
+    net::{
+        namespace::NetNamespace,
+        socket::{
+            netlink::{
+                message::ProtocolSegment,
+                route::kernel::get_netlink_route_kernel,
+                table::{BoundHandle, MessageQueue},
+                NetlinkSocketAddr,
+            },
+            util::datagram_common,
+            SendRecvFlags,
         },
-        util::datagram_common,
-        SendRecvFlags,
     },
     prelude::*,
     util::{MultiRead, MultiWrite},
 };
 
 pub(super) struct BoundNetlinkRoute {
-    handle: BoundHandle,
+    handle: BoundHandle<RtnlMessage>,
     remote_addr: NetlinkSocketAddr,
-    receive_queue: Mutex<VecDeque<RtnlMessage>>,
+    receive_queue: MessageQueue<RtnlMessage>,
+    /// The network namespace where the requests are handled.
+    net_ns: Arc<NetNamespace>,
 }
 
 impl BoundNetlinkRoute {
-    pub(super) const fn new(handle: BoundHandle) -> Self {
+    pub(super) const fn new(
+        handle: BoundHandle<RtnlMessage>,
+        receive_queue: MessageQueue<RtnlMessage>,
+        net_ns: Arc<NetNamespace>,
+    ) -> Self {
         Self {
             handle,
             remote_addr: NetlinkSocketAddr::new_unspecified(),
-            receive_queue: Mutex::new(VecDeque::new()),
+            receive_queue,
+            net_ns,
         }
     }
 }
@@ -97,7 +109,7 @@ impl datagram_common::Bound for BoundNetlinkRoute {
             }
         }
 
-        get_netlink_route_kernel().request(&nlmsg, |response| {
+        get_netlink_route_kernel().request(&self.net_ns, &nlmsg, |response| {
             self.receive_queue.lock().push_back(response);
         });
 
@@ -132,6 +144,7 @@ impl datagram_common::Bound for BoundNetlinkRoute {
         }
 
         // TODO: The message can only come from kernel socket currently.
+        // This includes the responses to the requests and the multicast notifications.
         let remote = NetlinkSocketAddr::new_unspecified();
 
         Ok((len, remote))
@@ -4,22 +4,35 @@
 
 use core::num::NonZeroU32;
 
-use super::util::finish_response;
+use aster_bigtcp::wire::{Ipv4Address, Ipv4Cidr};
+
+use super::util::{check_net_admin, finish_response};
 use crate::{
     net::{
-        iface::{iter_all_ifaces, Iface},
+        iface::Iface,
+        namespace::NetNamespace,
         socket::netlink::{
-            message::{CMsgSegHdr, CSegmentType, GetRequestFlags, SegHdrCommonFlags},
-            route::message::{
-                AddrAttr, AddrMessageFlags, AddrSegment, AddrSegmentBody, RtScope, RtnlSegment,
+            message::{
+                CMsgSegHdr, CSegmentType, GetRequestFlags, NewRequestFlags, SegHdrCommonFlags,
+            },
+            route::{
+                message::{
+                    AddrAttr, AddrMessageFlags, AddrSegment, AddrSegmentBody, RtScope, RtnlMessage,
+                    RtnlSegment,
+                },
+                NETLINK_ROUTE_SOCKET_TABLE,
             },
+            GroupIdSet,
         },
     },
     prelude::*,
     util::net::CSocketAddrFamily,
 };
 
-pub(super) fn do_get_addr(request_segment: &AddrSegment) -> Result<Vec<RtnlSegment>> {
+pub(super) fn do_get_addr(
+    net_ns: &NetNamespace,
+    request_segment: &AddrSegment,
+) -> Result<Vec<RtnlSegment>> {
     let dump_all = {
         let flags = GetRequestFlags::from_bits_truncate(request_segment.header().flags);
         flags.contains(GetRequestFlags::DUMP)
@@ -28,9 +41,19 @@ pub(super) fn do_get_addr(request_segment: &AddrSegment) -> Result<Vec<RtnlSegme
         return_errno_with_message!(Errno::EOPNOTSUPP, "GETADDR only supports dump requests");
     }
 
-    let mut response_segments: Vec<RtnlSegment> = iter_all_ifaces()
+    let mut response_segments: Vec<RtnlSegment> = net_ns
+        .ifaces()
+        .iter()
         // GETADDR only supports dump mode, so we're going to report all addresses.
-        .filter_map(|iface| iface_to_new_addr(request_segment.header(), iface))
+        .filter_map(|iface| {
+            let ipv4_cidr = iface.ipv4_cidr()?;
+            Some(iface_to_addr(
+                CSegmentType::NEWADDR,
+                request_segment.header(),
+                iface,
+                ipv4_cidr,
+            ))
+        })
         .map(RtnlSegment::NewAddr)
         .collect();
 
@@ -39,12 +62,154 @@ pub(super) fn do_get_addr(request_segment: &AddrSegment) -> Result<Vec<RtnlSegme
     Ok(response_segments)
 }
 
-fn iface_to_new_addr(request_header: &CMsgSegHdr, iface: &Arc<Iface>) -> Option<AddrSegment> {
-    let ipv4_addr = iface.ipv4_addr()?;
+pub(super) fn do_new_addr(
+    net_ns: &NetNamespace,
+    request_segment: &AddrSegment,
+) -> Result<Vec<RtnlSegment>> {
+    check_net_admin(net_ns)?;
+
+    let request = AddrRequest::from_segment(net_ns, request_segment)?;
+    let iface = &request.iface;
+
+    // Linux treats `IFA_ADDRESS` as `IFA_LOCAL` if the latter is missing.
+    let Some(local) = request.local.or(request.address) else {
+        return_errno_with_message!(Errno::EINVAL, "the address is not specified");
+    };
+    let new_cidr = Ipv4Cidr::new(local, request.prefix_len);
+
+    let flags = NewRequestFlags::from_bits_truncate(request_segment.header().flags);
+    match iface.ipv4_cidr() {
+        Some(old_cidr) if old_cidr.address() == local => {
+            if flags.contains(NewRequestFlags::EXCL) || !flags.contains(NewRequestFlags::REPLACE) {
+                return_errno_with_message!(Errno::EEXIST, "the address already exists");
+            }
+        }
+        Some(_) => {
+            // FIXME: One iface may have multiple IPv4 addresses.
+            return_errno_with_message!(
+                Errno::EOPNOTSUPP,
+                "adding a second IPv4 address to an interface is not supported"
+            );
+        }
+        None => (),
+    }
+
+    iface.set_ipv4_cidr(Some(new_cidr));
+
+    let new_addr = iface_to_addr(
+        CSegmentType::NEWADDR,
+        request_segment.header(),
+        iface,
+        new_cidr,
+    );
+    notify_addr_changes(RtnlSegment::NewAddr(new_addr));
+
+    Ok(Vec::new())
+}
+
+pub(super) fn do_del_addr(
+    net_ns: &NetNamespace,
+    request_segment: &AddrSegment,
+) -> Result<Vec<RtnlSegment>> {
+    check_net_admin(net_ns)?;
+
+    let request = AddrRequest::from_segment(net_ns, request_segment)?;
+    let iface = &request.iface;
+
+    // The attributes that are specified must match the address to delete, as in Linux.
+    let Some(old_cidr) = iface.ipv4_cidr().filter(|old_cidr| {
+        request
+            .local
+            .is_none_or(|local| local == old_cidr.address())
+            && request.label.is_none_or(|label| label == iface.name())
+            && request.address.is_none_or(|address| {
+                request.prefix_len == old_cidr.prefix_len() && old_cidr.contains_addr(&address)
+            })
+    }) else {
+        return_errno_with_message!(Errno::EADDRNOTAVAIL, "the address does not exist");
+    };
+
+    iface.set_ipv4_cidr(None);
+
+    let del_addr = iface_to_addr(
+        CSegmentType::DELADDR,
+        request_segment.header(),
+        iface,
+        old_cidr,
+    );
+    notify_addr_changes(RtnlSegment::DelAddr(del_addr));
+
+    Ok(Vec::new())
+}
+
+/// A parsed request that adds or deletes an address.
+struct AddrRequest<'a> {
+    iface: Arc<Iface>,
+    prefix_len: u8,
+    local: Option<Ipv4Address>,
+    address: Option<Ipv4Address>,
+    label: Option<&'a str>,
+}
+
+impl<'a> AddrRequest<'a> {
+    fn from_segment(net_ns: &NetNamespace, request_segment: &'a AddrSegment) -> Result<Self> {
+        let body = request_segment.body();
+
+        if body.family != CSocketAddrFamily::AF_INET as i32 {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "only IPv4 addresses are supported");
+        }
+        if body.prefix_len > 32 {
+            return_errno_with_message!(Errno::EINVAL, "the prefix length is invalid");
+        }
+
+        let Some(iface) = body
+            .index
+            .and_then(|index| net_ns.find_iface_by_index(index.get()))
+        else {
+            return_errno_with_message!(Errno::ENODEV, "the interface does not exist");
+        };
+
+        let mut request = Self {
+            iface,
+            prefix_len: body.prefix_len,
+            local: None,
+            address: None,
+            label: None,
+        };
+        for attr in request_segment.attrs().iter() {
+            match attr {
+                AddrAttr::Local(local) => request.local = Some(Ipv4Address::from(*local)),
+                AddrAttr::Address(address) => request.address = Some(Ipv4Address::from(*address)),
+                AddrAttr::Label(label) => request.label = label.to_str().ok(),
+                // The broadcast address is derived from the prefix length.
+                AddrAttr::Broadcast(_) => (),
+                // TODO: Support the address flags.
+                AddrAttr::Flags(_) => (),
+            }
+        }
+
+        Ok(request)
+    }
+}
+
+/// Sends the address changes to the sockets that are interested in them.
+fn notify_addr_changes(segment: RtnlSegment) {
+    let message = RtnlMessage::new(vec![segment]);
+    NETLINK_ROUTE_SOCKET_TABLE.multicast(GroupIdSet::new(RTMGRP_IPV4_IFADDR), message);
+}
+
+/// The multicast group mask for IPv4 address changes (`RTMGRP_IPV4_IFADDR` in Linux).
+const RTMGRP_IPV4_IFADDR: u32 = 0x10;
 
+fn iface_to_addr(
+    type_: CSegmentType,
+    request_header: &CMsgSegHdr,
+    iface: &Arc<Iface>,
+    ipv4_cidr: Ipv4Cidr,
+) -> AddrSegment {
     let header = CMsgSegHdr {
         len: 0,
-        type_: CSegmentType::NEWADDR as _,
+        type_: type_ as _,
         flags: SegHdrCommonFlags::empty().bits(),
         seq: request_header.seq,
         pid: request_header.pid,
@@ -52,17 +217,18 @@ fn iface_to_new_addr(request_header: &CMsgSegHdr, iface: &Arc<Iface>) -> Option<
 
     let addr_message = AddrSegmentBody {
         family: CSocketAddrFamily::AF_INET as _,
-        prefix_len: iface.prefix_len().unwrap(),
+        prefix_len: ipv4_cidr.prefix_len(),
         flags: AddrMessageFlags::PERMANENT,
         scope: RtScope::HOST,
         index: NonZeroU32::new(iface.index()),
     };
 
+    let ipv4_addr = ipv4_cidr.address();
     let attrs = vec![
         AddrAttr::Address(ipv4_addr.octets()),
         AddrAttr::Label(CString::new(iface.name()).unwrap()),
         AddrAttr::Local(ipv4_addr.octets()),
     ];
 
-    Some(AddrSegment::new(header, addr_message, attrs))
+    AddrSegment::new(header, addr_message, attrs)
 }
@@ -2,34 +2,55 @@
 
 //! Handle link-related requests.
 
-use core::num::NonZero;
+use core::{num::NonZero, time::Duration};
 
-use aster_bigtcp::iface::InterfaceType;
+use aster_bigtcp::iface::{InterfaceFlags, InterfaceType};
 
-use super::util::finish_response;
+use super::util::{check_net_admin, finish_response};
 use crate::{
     net::{
-        iface::{iter_all_ifaces, Iface},
+        iface::{
+            virt::{new_bridge, new_veth_pair, VirtDevice, VirtDeviceKind},
+            Iface,
+        },
+        namespace::NetNamespace,
         socket::netlink::{
-            message::{CMsgSegHdr, CSegmentType, GetRequestFlags, SegHdrCommonFlags},
-            route::message::{LinkAttr, LinkSegment, LinkSegmentBody, RtnlSegment},
+            message::{
+                CMsgSegHdr, CSegmentType, GetRequestFlags, NestedAttrs, NewRequestFlags,
+                SegHdrCommonFlags,
+            },
+            route::message::{
+                BridgeAttr, LinkAttr, LinkInfoAttr, LinkSegment, LinkSegmentBody, RtnlSegment,
+                VethAttr,
+            },
         },
     },
     prelude::*,
+    process::process_table,
     util::net::CSocketAddrFamily,
 };
 
-pub(super) fn do_get_link(request_segment: &LinkSegment) -> Result<Vec<RtnlSegment>> {
+pub(super) fn do_get_link(
+    net_ns: &NetNamespace,
+    request_segment: &LinkSegment,
+) -> Result<Vec<RtnlSegment>> {
     let filter_by = FilterBy::from_request(request_segment)?;
 
-    let mut response_segments: Vec<RtnlSegment> = iter_all_ifaces()
+    let ifaces: Vec<Arc<Iface>> = net_ns
+        .ifaces()
+        .iter()
         // Filter to include only requested links.
         .filter(|iface| match &filter_by {
             FilterBy::Index(index) => *index == iface.index(),
             FilterBy::Name(name) => *name == iface.name(),
             FilterBy::Dump => true,
         })
-        .map(|iface| iface_to_new_link(request_segment.header(), iface))
+        .cloned()
+        .collect();
+
+    let mut response_segments: Vec<RtnlSegment> = ifaces
+        .iter()
+        .map(|iface| iface_to_new_link(net_ns, request_segment.header(), iface))
         .map(RtnlSegment::NewLink)
         .collect();
 
@@ -44,6 +65,356 @@ pub(super) fn do_get_link(request_segment: &LinkSegment) -> Result<Vec<RtnlSegme
     Ok(response_segments)
 }
 
+pub(super) fn do_new_link(
+    net_ns: &Arc<NetNamespace>,
+    request_segment: &LinkSegment,
+) -> Result<Vec<RtnlSegment>> {
+    check_net_admin(net_ns)?;
+
+    let flags = NewRequestFlags::from_bits_truncate(request_segment.header().flags);
+
+    let iface = match find_link(net_ns, request_segment) {
+        Ok(iface) => iface,
+        Err(_) if flags.contains(NewRequestFlags::CREATE) => {
+            create_link(net_ns, request_segment)?;
+            return Ok(Vec::new());
+        }
+        Err(error) => return Err(error),
+    };
+
+    if flags.contains(NewRequestFlags::EXCL) {
+        return_errno_with_message!(Errno::EEXIST, "the link already exists");
+    }
+    if flags.contains(NewRequestFlags::REPLACE) {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "replacing links is not supported");
+    }
+
+    // A NEWLINK request for an existing link is handled as a SETLINK request.
+    set_link(net_ns, &iface, request_segment)?;
+
+    Ok(Vec::new())
+}
+
+pub(super) fn do_set_link(
+    net_ns: &Arc<NetNamespace>,
+    request_segment: &LinkSegment,
+) -> Result<Vec<RtnlSegment>> {
+    check_net_admin(net_ns)?;
+
+    let iface = find_link(net_ns, request_segment)?;
+    set_link(net_ns, &iface, request_segment)?;
+
+    Ok(Vec::new())
+}
+
+pub(super) fn do_del_link(
+    net_ns: &NetNamespace,
+    request_segment: &LinkSegment,
+) -> Result<Vec<RtnlSegment>> {
+    check_net_admin(net_ns)?;
+
+    let iface = find_link(net_ns, request_segment)?;
+    let Some(device) = VirtDevice::from_iface(&iface) else {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "only virtual links can be deleted");
+    };
+    device.destroy();
+
+    Ok(Vec::new())
+}
+
+/// Creates a virtual link of the kind specified in the link information.
+///
+/// The link is added to the network namespace specified by `IFLA_NET_NS_PID`, or `net_ns` if
+/// the attribute is missing. Then the other attributes are applied as in a SETLINK request.
+fn create_link(net_ns: &Arc<NetNamespace>, request_segment: &LinkSegment) -> Result<()> {
+    if request_segment.body().index.is_some() {
+        return_errno_with_message!(
+            Errno::EOPNOTSUPP,
+            "specifying the index of a new link is not supported"
+        );
+    }
+
+    let Some(link_info) = find_link_info(request_segment.attrs()) else {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "the link kind is not specified");
+    };
+    let Some(kind) = find_kind(link_info)? else {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "the link kind is not specified");
+    };
+    let data = link_info.attrs().iter().find_map(|attr| match attr {
+        LinkInfoAttr::Data(data) => Some(data),
+        _ => None,
+    });
+
+    let target_net_ns = find_net_ns(request_segment.attrs())?.unwrap_or_else(|| net_ns.clone());
+    let name = match find_name(request_segment) {
+        Some(name) => name.to_string(),
+        None => alloc_name(&target_net_ns, kind, None),
+    };
+
+    let device = match kind {
+        VirtDeviceKind::Veth => {
+            let veth_attrs = data
+                .map(|data| NestedAttrs::<VethAttr>::parse(data.clone()))
+                .transpose()?;
+            let peer = veth_attrs
+                .as_ref()
+                .and_then(|veth_attrs| veth_attrs.attrs().iter().next())
+                .map(|VethAttr::Peer(peer)| peer);
+            if peer.is_some_and(|peer| peer.body().index.is_some()) {
+                return_errno_with_message!(
+                    Errno::EOPNOTSUPP,
+                    "specifying the index of a new link is not supported"
+                );
+            }
+            let peer_attrs = peer.map_or(&[][..], |peer| peer.attrs());
+
+            // As in Linux, the peer is added to the namespace of the request by default, not
+            // the namespace of the new link.
+            let peer_net_ns = find_net_ns(peer_attrs)?.unwrap_or_else(|| net_ns.clone());
+            let peer_name = match find_name_in(peer_attrs) {
+                Some(peer_name) => peer_name.to_string(),
+                None => {
+                    let reserved = Arc::ptr_eq(&peer_net_ns, &target_net_ns).then_some(&*name);
+                    alloc_name(&peer_net_ns, kind, reserved)
+                }
+            };
+
+            new_veth_pair(name, &target_net_ns, peer_name, &peer_net_ns)?
+        }
+        VirtDeviceKind::Bridge => {
+            let bridge_attrs = data
+                .map(|data| NestedAttrs::<BridgeAttr>::parse(data.clone()))
+                .transpose()?;
+            let ageing_time = bridge_attrs.as_ref().and_then(|bridge_attrs| {
+                bridge_attrs.attrs().iter().find_map(|attr| match attr {
+                    BridgeAttr::AgeingTime(centisecs) => {
+                        Some(Duration::from_millis(*centisecs as u64 * 10))
+                    }
+                })
+            });
+
+            new_bridge(name, &target_net_ns, ageing_time)?
+        }
+    };
+
+    // The link is destroyed if the other attributes cannot be applied, as in Linux.
+    let Some(iface) = device.iface() else {
+        return_errno_with_message!(Errno::ENODEV, "the link has been destroyed");
+    };
+    if let Err(error) = set_link(&target_net_ns, &iface, request_segment) {
+        device.destroy();
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+/// Allocates a name for a new link, which is the name of the kind followed by the smallest
+/// number that makes the name unused in the network namespace.
+fn alloc_name(net_ns: &NetNamespace, kind: VirtDeviceKind, reserved: Option<&str>) -> String {
+    (0..)
+        .map(|number| format!("{}{}", kind.name(), number))
+        .find(|name| Some(name.as_str()) != reserved && net_ns.find_iface_by_name(name).is_none())
+        .unwrap()
+}
+
+/// Finds the link specified by the interface index or the interface name.
+fn find_link(net_ns: &NetNamespace, request_segment: &LinkSegment) -> Result<Arc<Iface>> {
+    let iface = if let Some(required_index) = request_segment.body().index {
+        net_ns.find_iface_by_index(required_index.get())
+    } else if let Some(required_name) = find_name(request_segment) {
+        net_ns.find_iface_by_name(required_name)
+    } else {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "either interface name or index should be specified"
+        );
+    };
+
+    iface.ok_or_else(|| Error::with_message(Errno::ENODEV, "no link found"))
+}
+
+/// Applies the changes in the request to the link in the network namespace.
+///
+/// Currently, only the master and the network namespace of the virtual links can be changed.
+/// For other attributes, the request succeeds only if it does not change anything, e.g.,
+/// bringing up a link that is already up.
+fn set_link(
+    net_ns: &Arc<NetNamespace>,
+    iface: &Arc<Iface>,
+    request_segment: &LinkSegment,
+) -> Result<()> {
+    let body = request_segment.body();
+
+    if !body.flags.is_empty() || !body.change.is_empty() {
+        // A zero change mask means that all the flags should be changed, as in Linux.
+        let change = if body.change.is_empty() {
+            InterfaceFlags::all()
+        } else {
+            body.change
+        };
+        // The other flags reflect the device state and cannot be changed by user space.
+        let changeable = InterfaceFlags::UP
+            | InterfaceFlags::DEBUG
+            | InterfaceFlags::NOTRAILERS
+            | InterfaceFlags::NOARP
+            | InterfaceFlags::PROMISC
+            | InterfaceFlags::ALLMULTI
+            | InterfaceFlags::MULTICAST
+            | InterfaceFlags::PORTSEL
+            | InterfaceFlags::AUTOMEDIA
+            | InterfaceFlags::DYNAMIC;
+
+        if !((body.flags ^ iface.flags()) & change & changeable).is_empty() {
+            return_errno_with_message!(
+                Errno::EOPNOTSUPP,
+                "changing the interface flags is not supported"
+            );
+        }
+    }
+
+    for attr in request_segment.attrs().iter() {
+        match attr {
+            LinkAttr::Name(name) if body.index.is_some() => {
+                if name.to_bytes() != iface.name().as_bytes() {
+                    return_errno_with_message!(
+                        Errno::EOPNOTSUPP,
+                        "renaming the interface is not supported"
+                    );
+                }
+            }
+            LinkAttr::Mtu(mtu) => {
+                if *mtu as usize != iface.mtu() {
+                    return_errno_with_message!(
+                        Errno::EOPNOTSUPP,
+                        "changing the interface MTU is not supported"
+                    );
+                }
+            }
+            LinkAttr::Master(0) => {
+                if let Some(device) = VirtDevice::from_iface(iface) {
+                    device.detach();
+                }
+            }
+            LinkAttr::Master(master_index) => {
+                let Some(master_iface) = net_ns.find_iface_by_index(*master_index) else {
+                    return_errno_with_message!(Errno::EINVAL, "the master link does not exist");
+                };
+                let (Some(device), Some(master)) = (
+                    VirtDevice::from_iface(iface),
+                    VirtDevice::from_iface(&master_iface),
+                ) else {
+                    return_errno_with_message!(
+                        Errno::EOPNOTSUPP,
+                        "only virtual links can be attached to bridges"
+                    );
+                };
+                device.set_master(&master)?;
+            }
+            LinkAttr::NetNsPid(_) | LinkAttr::NetNsFd(_) => {
+                let target_net_ns = find_net_ns(core::slice::from_ref(attr))?.unwrap();
+                let Some(device) = VirtDevice::from_iface(iface) else {
+                    return_errno_with_message!(
+                        Errno::EINVAL,
+                        "only virtual links can be moved to another network namespace"
+                    );
+                };
+                device.move_to(&target_net_ns)?;
+            }
+            LinkAttr::LinkInfo(link_info) => {
+                // The data specific to the kind is only used when the link is created.
+                let kind = VirtDevice::from_iface(iface).map(|device| device.kind());
+                if find_kind(link_info)?.is_some_and(|new_kind| Some(new_kind) != kind) {
+                    return_errno_with_message!(
+                        Errno::EOPNOTSUPP,
+                        "changing the link kind is not supported"
+                    );
+                }
+            }
+            // The name has been used to find the link.
+            LinkAttr::Name(_) => (),
+            attr => {
+                // TODO: Support changing other link attributes.
+                warn!("link attribute `{:?}` is ignored", attr);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn find_name(request_segment: &LinkSegment) -> Option<&str> {
+    find_name_in(request_segment.attrs())
+}
+
+fn find_name_in(attrs: &[LinkAttr]) -> Option<&str> {
+    attrs.iter().find_map(|attr| {
+        if let LinkAttr::Name(name) = attr {
+            Some(name.to_str().unwrap())
+        } else {
+            None
+        }
+    })
+}
+
+fn find_link_info(attrs: &[LinkAttr]) -> Option<&NestedAttrs<LinkInfoAttr>> {
+    attrs.iter().find_map(|attr| {
+        if let LinkAttr::LinkInfo(link_info) = attr {
+            Some(link_info)
+        } else {
+            None
+        }
+    })
+}
+
+/// Finds the link kind in the link information.
+///
+/// # Errors
+///
+/// This function will fail with [`Errno::EOPNOTSUPP`] if the kind is not supported.
+fn find_kind(link_info: &NestedAttrs<LinkInfoAttr>) -> Result<Option<VirtDeviceKind>> {
+    let Some(name) = link_info.attrs().iter().find_map(|attr| match attr {
+        LinkInfoAttr::Kind(name) => Some(name),
+        _ => None,
+    }) else {
+        return Ok(None);
+    };
+
+    name.to_str()
+        .ok()
+        .and_then(VirtDeviceKind::from_name)
+        .map(Some)
+        .ok_or_else(|| Error::with_message(Errno::EOPNOTSUPP, "the link kind is not supported"))
+}
+
+/// Finds the network namespace specified by the attributes.
+///
+/// The current thread must have `CAP_NET_ADMIN` in the network namespace.
+fn find_net_ns(attrs: &[LinkAttr]) -> Result<Option<Arc<NetNamespace>>> {
+    for attr in attrs.iter() {
+        match attr {
+            LinkAttr::NetNsPid(pid) => {
+                let Some(process) = process_table::get_process(*pid) else {
+                    return_errno_with_message!(Errno::ESRCH, "the process does not exist");
+                };
+                let net_ns = process.net_ns();
+                check_net_admin(&net_ns)?;
+                return Ok(Some(net_ns));
+            }
+            LinkAttr::NetNsFd(_) => {
+                // TODO: Support the network namespace files (i.e., `/proc/[pid]/ns/net`).
+                return_errno_with_message!(
+                    Errno::EOPNOTSUPP,
+                    "specifying network namespaces by file descriptors is not supported"
+                );
+            }
+            _ => (),
+        }
+    }
+
+    Ok(None)
+}
+
 enum FilterBy<'a> {
     Index(u32),
     Name(&'a str),
@@ -69,14 +440,7 @@ impl<'a> FilterBy<'a> {
             return Ok(Self::Index(required_index.get()));
         }
 
-        let required_name = request_segment.attrs().iter().find_map(|attr| {
-            if let LinkAttr::Name(name) = attr {
-                Some(name.to_str().unwrap())
-            } else {
-                None
-            }
-        });
-        if let Some(required_name) = required_name {
+        if let Some(required_name) = find_name(request_segment) {
             return Ok(Self::Name(required_name));
         }
 
@@ -91,22 +455,27 @@ impl<'a> FilterBy<'a> {
 // Reference: <https://docs.kernel.org/userspace-api/netlink/intro.html#strict-checking>.
 
 fn validate_getlink_request(body: &LinkSegmentBody) -> Result<()> {
-    // FIXME: The Linux implementation also checks the `padding` and `change` fields,
-    // but these fields are lost during the conversion of a `CIfInfoMsg` to `LinkSegmentBody`.
-    // We should consider including the `change` field in `LinkSegmentBody`.
+    // FIXME: The Linux implementation also checks the `padding` field,
+    // but this field is lost during the conversion of a `CIfInfoMsg` to `LinkSegmentBody`.
     // Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/core/rtnetlink.c#L4043>.
-    if !body.flags.is_empty() || body.type_ != InterfaceType::NETROM {
-        return_errno_with_message!(Errno::EINVAL, "the flags or the type is not valid");
+    if !body.flags.is_empty() || !body.change.is_empty() || body.type_ != InterfaceType::NETROM {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the flags, the change mask, or the type is not valid"
+        );
     }
 
     Ok(())
 }
 
 fn validate_dumplink_request(body: &LinkSegmentBody) -> Result<()> {
-    // FIXME: The Linux implementation also checks the `padding` and `change` fields.
+    // FIXME: The Linux implementation also checks the `padding` field.
     // Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/core/rtnetlink.c#L2378>.
-    if !body.flags.is_empty() || body.type_ != InterfaceType::NETROM {
-        return_errno_with_message!(Errno::EINVAL, "the flags or the type is not valid");
+    if !body.flags.is_empty() || !body.change.is_empty() || body.type_ != InterfaceType::NETROM {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the flags, the change mask, or the type is not valid"
+        );
     }
 
     // The check is from <https://elixir.bootlin.com/linux/v6.13/source/net/core/rtnetlink.c#L2383>.
@@ -120,7 +489,11 @@ fn validate_dumplink_request(body: &LinkSegmentBody) -> Result<()> {
     Ok(())
 }
 
-fn iface_to_new_link(request_header: &CMsgSegHdr, iface: &Arc<Iface>) -> LinkSegment {
+fn iface_to_new_link(
+    net_ns: &NetNamespace,
+    request_header: &CMsgSegHdr,
+    iface: &Arc<Iface>,
+) -> LinkSegment {
     let header = CMsgSegHdr {
         len: 0,
         type_: CSegmentType::NEWLINK as _,
@@ -134,12 +507,32 @@ fn iface_to_new_link(request_header: &CMsgSegHdr, iface: &Arc<Iface>) -> LinkSeg
         type_: iface.type_(),
         index: NonZero::new(iface.index()),
         flags: iface.flags(),
+        change: InterfaceFlags::empty(),
     };
 
-    let attrs = vec![
+    let mut attrs = vec![
         LinkAttr::Name(CString::new(iface.name()).unwrap()),
         LinkAttr::Mtu(iface.mtu() as u32),
     ];
 
+    if let Some(device) = VirtDevice::from_iface(iface) {
+        let kind = CString::new(device.kind().name()).unwrap();
+        attrs.push(LinkAttr::LinkInfo(NestedAttrs::new(vec![
+            LinkInfoAttr::Kind(kind),
+        ])));
+
+        if let Some(master) = device.master().and_then(|master| master.iface()) {
+            attrs.push(LinkAttr::Master(master.index()));
+        }
+
+        // FIXME: Linux reports the peer in another network namespace with
+        // `IFLA_LINK_NETNSID`, which is not supported.
+        if let Some(peer) = device.peer().and_then(|peer| peer.iface())
+            && net_ns.find_iface_by_index(peer.index()).is_some()
+        {
+            attrs.push(LinkAttr::Link(peer.index()));
+        }
+    }
+
     LinkSegment::new(header, link_message, attrs)
 }
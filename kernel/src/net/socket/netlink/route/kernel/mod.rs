@@ -3,11 +3,14 @@
 //! This module defines the kernel socket,
 //! which is responsible for handling requests from user space.
 
-use core::marker::PhantomData;
-
 use super::message::{RtnlMessage, RtnlSegment};
 use crate::{
-    net::socket::netlink::message::{CSegmentType, ErrorSegment, ProtocolSegment},
+    net::{
+        namespace::NetNamespace,
+        socket::netlink::message::{
+            CSegmentType, ErrorSegment, ProtocolSegment, SegHdrCommonFlags,
+        },
+    },
     prelude::*,
 };
 
@@ -16,31 +19,41 @@ mod link;
 mod util;
 
 pub(super) struct NetlinkRouteKernelSocket {
-    _private: PhantomData<()>,
+    /// A lock that serializes the requests, which is similar to `rtnl_lock` in Linux.
+    request_lock: Mutex<()>,
 }
 
 impl NetlinkRouteKernelSocket {
     const fn new() -> Self {
         Self {
-            _private: PhantomData,
+            request_lock: Mutex::new(()),
         }
     }
 
+    /// Handles the request in the network namespace.
     pub(super) fn request<F: FnMut(RtnlMessage)>(
         &self,
+        net_ns: &Arc<NetNamespace>,
         request: &RtnlMessage,
         mut consume_response: F,
     ) {
         debug!("netlink route request: {:?}", request);
 
+        let _guard = self.request_lock.lock();
+
         for segment in request.segments() {
             let request_header = segment.header();
 
             let segment_type = CSegmentType::try_from(request_header.type_).unwrap();
 
             let response_segments = match segment {
-                RtnlSegment::GetLink(request_segment) => link::do_get_link(request_segment),
-                RtnlSegment::GetAddr(request_segment) => addr::do_get_addr(request_segment),
+                RtnlSegment::NewLink(request_segment) => link::do_new_link(net_ns, request_segment),
+                RtnlSegment::DelLink(request_segment) => link::do_del_link(net_ns, request_segment),
+                RtnlSegment::GetLink(request_segment) => link::do_get_link(net_ns, request_segment),
+                RtnlSegment::SetLink(request_segment) => link::do_set_link(net_ns, request_segment),
+                RtnlSegment::NewAddr(request_segment) => addr::do_new_addr(net_ns, request_segment),
+                RtnlSegment::DelAddr(request_segment) => addr::do_del_addr(net_ns, request_segment),
+                RtnlSegment::GetAddr(request_segment) => addr::do_get_addr(net_ns, request_segment),
                 _ => {
                     // FIXME: The error is currently silently ignored.
                     warn!("unsupported request type: {:?}", segment_type);
@@ -49,6 +62,16 @@ impl NetlinkRouteKernelSocket {
             };
 
             let response = match response_segments {
+                // A request that modifies the kernel state has no response segments. An
+                // acknowledgment is sent only if it is requested.
+                Ok(segments) if segments.is_empty() => {
+                    let flags = SegHdrCommonFlags::from_bits_truncate(request_header.flags);
+                    if !flags.contains(SegHdrCommonFlags::ACK) {
+                        continue;
+                    }
+                    let ack_segment = ErrorSegment::new_from_request(request_header, None);
+                    RtnlMessage::new(vec![RtnlSegment::Error(ack_segment)])
+                }
                 Ok(segments) => RtnlMessage::new(segments),
                 Err(error) => {
                     // TODO: Deal with the `NetlinkMessageCommonFlags::ACK` flag.
@@ -1,13 +1,31 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use crate::{
-    net::socket::netlink::{
-        message::{CMsgSegHdr, DoneSegment, ProtocolSegment, SegHdrCommonFlags},
-        route::message::RtnlSegment,
+    net::{
+        namespace::NetNamespace,
+        socket::netlink::{
+            message::{CMsgSegHdr, DoneSegment, ProtocolSegment, SegHdrCommonFlags},
+            route::message::RtnlSegment,
+        },
     },
     prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
 };
 
+/// Checks whether the current thread is allowed to modify the configuration of the network
+/// namespace.
+pub fn check_net_admin(net_ns: &NetNamespace) -> Result<()> {
+    let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+    if !credentials.has_capability_in(CapSet::NET_ADMIN, net_ns.owner()) {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "modifying the network configuration requires `CAP_NET_ADMIN`"
+        );
+    }
+
+    Ok(())
+}
+
 /// Finishes a response message.
 pub fn finish_response(
     request_header: &CMsgSegHdr,
@@ -27,11 +27,13 @@ enum AddrAttrClass {
     TARGET_NETNSID = 10,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum AddrAttr {
     Address([u8; 4]),
     Local([u8; 4]),
     Label(CString),
+    Broadcast([u8; 4]),
+    Flags(u32),
 }
 
 impl AddrAttr {
@@ -40,6 +42,8 @@ impl AddrAttr {
             AddrAttr::Address(_) => AddrAttrClass::ADDRESS,
             AddrAttr::Local(_) => AddrAttrClass::LOCAL,
             AddrAttr::Label(_) => AddrAttrClass::LABEL,
+            AddrAttr::Broadcast(_) => AddrAttrClass::BROADCAST,
+            AddrAttr::Flags(_) => AddrAttrClass::FLAGS,
         }
     }
 }
@@ -54,6 +58,8 @@ impl Attribute for AddrAttr {
             AddrAttr::Address(address) => address,
             AddrAttr::Local(local) => local,
             AddrAttr::Label(label) => label.as_bytes_with_nul(),
+            AddrAttr::Broadcast(broadcast) => broadcast,
+            AddrAttr::Flags(flags) => flags.as_bytes(),
         }
     }
 
@@ -67,6 +73,8 @@ impl Attribute for AddrAttr {
             AddrAttrClass::ADDRESS => Self::Address(reader.read_val()?),
             AddrAttrClass::LOCAL => Self::Local(reader.read_val()?),
             AddrAttrClass::LABEL => Self::Label(reader.read_cstring_with_max_len(IFNAME_SIZE)?),
+            AddrAttrClass::BROADCAST => Self::Broadcast(reader.read_val()?),
+            AddrAttrClass::FLAGS => Self::Flags(reader.read_val()?),
             class => {
                 // FIXME: Netlink should ignore all unknown attributes.
                 // See the reference in `LinkAttr::read_from`.
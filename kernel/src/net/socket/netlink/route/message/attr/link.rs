@@ -1,8 +1,8 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use super::IFNAME_SIZE;
+use super::{link_info::LinkInfoAttr, IFNAME_SIZE};
 use crate::{
-    net::socket::netlink::message::{Attribute, CAttrHeader},
+    net::socket::netlink::message::{Attribute, CAttrHeader, NestedAttrs},
     prelude::*,
     util::MultiRead,
 };
@@ -81,13 +81,20 @@ enum LinkAttrClass {
     PARENT_DEV_BUS_NAME = 57,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum LinkAttr {
     Name(CString),
     Mtu(u32),
+    /// The index of the link that the link is connected to (e.g., the peer of a veth device).
+    Link(u32),
+    /// The index of the master device (e.g., the bridge that the link is attached to).
+    Master(u32),
     TxqLen(u32),
     LinkMode(u8),
+    LinkInfo(NestedAttrs<LinkInfoAttr>),
+    NetNsPid(u32),
     ExtMask(RtExtFilter),
+    NetNsFd(u32),
 }
 
 impl LinkAttr {
@@ -95,9 +102,14 @@ impl LinkAttr {
         match self {
             LinkAttr::Name(_) => LinkAttrClass::IFNAME,
             LinkAttr::Mtu(_) => LinkAttrClass::MTU,
+            LinkAttr::Link(_) => LinkAttrClass::LINK,
+            LinkAttr::Master(_) => LinkAttrClass::MASTER,
             LinkAttr::TxqLen(_) => LinkAttrClass::TXQLEN,
             LinkAttr::LinkMode(_) => LinkAttrClass::LINKMODE,
+            LinkAttr::LinkInfo(_) => LinkAttrClass::LINKINFO,
+            LinkAttr::NetNsPid(_) => LinkAttrClass::NET_NS_PID,
             LinkAttr::ExtMask(_) => LinkAttrClass::EXT_MASK,
+            LinkAttr::NetNsFd(_) => LinkAttrClass::NET_NS_FD,
         }
     }
 }
@@ -111,9 +123,14 @@ impl Attribute for LinkAttr {
         match self {
             LinkAttr::Name(name) => name.as_bytes_with_nul(),
             LinkAttr::Mtu(mtu) => mtu.as_bytes(),
+            LinkAttr::Link(index) => index.as_bytes(),
+            LinkAttr::Master(index) => index.as_bytes(),
             LinkAttr::TxqLen(txq_len) => txq_len.as_bytes(),
             LinkAttr::LinkMode(link_mode) => link_mode.as_bytes(),
+            LinkAttr::LinkInfo(link_info) => link_info.as_bytes(),
+            LinkAttr::NetNsPid(pid) => pid.as_bytes(),
             LinkAttr::ExtMask(ext_filter) => ext_filter.as_bytes(),
+            LinkAttr::NetNsFd(fd) => fd.as_bytes(),
         }
     }
 
@@ -126,9 +143,14 @@ impl Attribute for LinkAttr {
         let res = match LinkAttrClass::try_from(header.type_())? {
             LinkAttrClass::IFNAME => Self::Name(reader.read_cstring_with_max_len(IFNAME_SIZE)?),
             LinkAttrClass::MTU => Self::Mtu(reader.read_val()?),
+            LinkAttrClass::LINK => Self::Link(reader.read_val()?),
+            LinkAttrClass::MASTER => Self::Master(reader.read_val()?),
             LinkAttrClass::TXQLEN => Self::TxqLen(reader.read_val()?),
             LinkAttrClass::LINKMODE => Self::LinkMode(reader.read_val()?),
+            LinkAttrClass::LINKINFO => Self::LinkInfo(NestedAttrs::read_from(&header, reader)?),
+            LinkAttrClass::NET_NS_PID => Self::NetNsPid(reader.read_val()?),
             LinkAttrClass::EXT_MASK => Self::ExtMask(reader.read_val()?),
+            LinkAttrClass::NET_NS_FD => Self::NetNsFd(reader.read_val()?),
             class => {
                 // FIXME: Netlink should ignore all unknown attributes.
                 // But how to decide the payload type if the class is unknown?
@@ -0,0 +1,223 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Attributes nested in the link information (i.e., `IFLA_LINKINFO`).
+
+use super::link::LinkAttr;
+use crate::{
+    net::socket::netlink::{
+        message::{Attribute, CAttrHeader},
+        route::message::segment::link::{CIfinfoMsg, LinkSegmentBody},
+    },
+    prelude::*,
+    util::MultiRead,
+};
+
+/// Link information attributes.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/if_link.h>.
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[repr(u16)]
+#[expect(non_camel_case_types)]
+enum LinkInfoAttrClass {
+    UNSPEC = 0,
+    KIND = 1,
+    DATA = 2,
+    XSTATS = 3,
+    SLAVE_KIND = 4,
+    SLAVE_DATA = 5,
+}
+
+#[derive(Debug, Clone)]
+pub enum LinkInfoAttr {
+    /// The kind of the link (e.g., `veth` or `bridge`).
+    Kind(CString),
+    /// The data specific to the kind, which should be parsed according to the kind.
+    Data(Vec<u8>),
+}
+
+impl LinkInfoAttr {
+    fn class(&self) -> LinkInfoAttrClass {
+        match self {
+            LinkInfoAttr::Kind(_) => LinkInfoAttrClass::KIND,
+            LinkInfoAttr::Data(_) => LinkInfoAttrClass::DATA,
+        }
+    }
+}
+
+impl Attribute for LinkInfoAttr {
+    fn type_(&self) -> u16 {
+        self.class() as u16
+    }
+
+    fn payload_as_bytes(&self) -> &[u8] {
+        match self {
+            LinkInfoAttr::Kind(kind) => kind.as_bytes_with_nul(),
+            LinkInfoAttr::Data(data) => data,
+        }
+    }
+
+    fn read_from(reader: &mut dyn MultiRead) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        let header = reader.read_val::<CAttrHeader>()?;
+        let res = match LinkInfoAttrClass::try_from(header.type_())? {
+            LinkInfoAttrClass::KIND => {
+                Self::Kind(reader.read_cstring_with_max_len(header.payload_len()?)?)
+            }
+            LinkInfoAttrClass::DATA => Self::Data(header.read_payload(reader)?),
+            class => {
+                warn!("link information attribute `{:?}` is not supported", class);
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "unsupported link information attribute"
+                );
+            }
+        };
+
+        Ok(res)
+    }
+}
+
+/// Veth attributes, which are the data of the `veth` links.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/veth.h>.
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[repr(u16)]
+#[expect(non_camel_case_types)]
+enum VethAttrClass {
+    UNSPEC = 0,
+    PEER = 1,
+}
+
+#[derive(Debug, Clone)]
+pub enum VethAttr {
+    /// The other end of the veth pair.
+    Peer(VethPeer),
+}
+
+impl VethAttr {
+    fn class(&self) -> VethAttrClass {
+        match self {
+            VethAttr::Peer(_) => VethAttrClass::PEER,
+        }
+    }
+}
+
+impl Attribute for VethAttr {
+    fn type_(&self) -> u16 {
+        self.class() as u16
+    }
+
+    fn payload_as_bytes(&self) -> &[u8] {
+        match self {
+            VethAttr::Peer(peer) => &peer.bytes,
+        }
+    }
+
+    fn read_from(reader: &mut dyn MultiRead) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        let header = reader.read_val::<CAttrHeader>()?;
+        let res = match VethAttrClass::try_from(header.type_())? {
+            VethAttrClass::PEER => Self::Peer(VethPeer::parse(header.read_payload(reader)?)?),
+            class => {
+                warn!("veth attribute `{:?}` is not supported", class);
+                return_errno_with_message!(Errno::EINVAL, "unsupported veth attribute");
+            }
+        };
+
+        Ok(res)
+    }
+}
+
+/// The other end of a veth pair, which is described by a link message body and link
+/// attributes.
+#[derive(Debug, Clone)]
+pub struct VethPeer {
+    body: LinkSegmentBody,
+    attrs: Vec<LinkAttr>,
+    bytes: Vec<u8>,
+}
+
+impl VethPeer {
+    fn parse(bytes: Vec<u8>) -> Result<Self> {
+        let Some(attrs_len) = bytes.len().checked_sub(size_of::<CIfinfoMsg>()) else {
+            return_errno_with_message!(Errno::EINVAL, "the veth peer is too small");
+        };
+
+        let reader: &mut dyn MultiRead = &mut VmReader::from(bytes.as_slice()).to_fallible();
+        let body = LinkSegmentBody::try_from(reader.read_val::<CIfinfoMsg>()?)?;
+        let attrs = LinkAttr::read_all_from(reader, attrs_len)?;
+
+        Ok(Self { body, attrs, bytes })
+    }
+
+    pub fn body(&self) -> &LinkSegmentBody {
+        &self.body
+    }
+
+    pub fn attrs(&self) -> &[LinkAttr] {
+        &self.attrs
+    }
+}
+
+/// Bridge attributes, which are the data of the `bridge` links.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/if_link.h>.
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[repr(u16)]
+#[expect(non_camel_case_types)]
+enum BridgeAttrClass {
+    UNSPEC = 0,
+    FORWARD_DELAY = 1,
+    HELLO_TIME = 2,
+    MAX_AGE = 3,
+    AGEING_TIME = 4,
+    STP_STATE = 5,
+    PRIORITY = 6,
+    VLAN_FILTERING = 7,
+}
+
+#[derive(Debug, Clone)]
+pub enum BridgeAttr {
+    /// The ageing time of the FDB entries, in centiseconds.
+    AgeingTime(u32),
+}
+
+impl BridgeAttr {
+    fn class(&self) -> BridgeAttrClass {
+        match self {
+            BridgeAttr::AgeingTime(_) => BridgeAttrClass::AGEING_TIME,
+        }
+    }
+}
+
+impl Attribute for BridgeAttr {
+    fn type_(&self) -> u16 {
+        self.class() as u16
+    }
+
+    fn payload_as_bytes(&self) -> &[u8] {
+        match self {
+            BridgeAttr::AgeingTime(ageing_time) => ageing_time.as_bytes(),
+        }
+    }
+
+    fn read_from(reader: &mut dyn MultiRead) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        let header = reader.read_val::<CAttrHeader>()?;
+        let res = match BridgeAttrClass::try_from(header.type_())? {
+            BridgeAttrClass::AGEING_TIME => Self::AgeingTime(reader.read_val()?),
+            class => {
+                warn!("bridge attribute `{:?}` is not supported", class);
+                return_errno_with_message!(Errno::EINVAL, "unsupported bridge attribute");
+            }
+        };
+
+        Ok(res)
+    }
+}
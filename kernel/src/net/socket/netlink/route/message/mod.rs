@@ -8,7 +8,11 @@
 mod attr;
 mod segment;
 
-pub(super) use attr::{addr::AddrAttr, link::LinkAttr};
+pub(super) use attr::{
+    addr::AddrAttr,
+    link::LinkAttr,
+    link_info::{BridgeAttr, LinkInfoAttr, VethAttr},
+};
 pub(super) use segment::{
     addr::{AddrMessageFlags, AddrSegment, AddrSegmentBody, RtScope},
     link::{LinkSegment, LinkSegmentBody},
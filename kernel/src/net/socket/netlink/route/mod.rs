@@ -5,16 +5,20 @@
 use core::sync::atomic::{AtomicBool, Ordering};
 
 use bound::BoundNetlinkRoute;
+use message::RtnlMessage;
 use unbound::UnboundNetlinkRoute;
 
-use super::NetlinkSocketAddr;
+use super::{table::ProtocolSocketTable, NetlinkSocketAddr};
 use crate::{
     events::IoEvents,
-    net::socket::{
-        options::SocketOption,
-        private::SocketPrivate,
-        util::datagram_common::{select_remote_and_bind, Bound, Inner},
-        MessageHeader, SendRecvFlags, Socket, SocketAddr,
+    net::{
+        namespace::NetNamespace,
+        socket::{
+            options::SocketOption,
+            private::SocketPrivate,
+            util::datagram_common::{select_remote_and_bind, Bound, Inner},
+            MessageHeader, SendRecvFlags, Socket, SocketAddr,
+        },
     },
     prelude::*,
     process::signal::{PollHandle, Pollable, Pollee},
@@ -26,19 +30,27 @@ mod kernel;
 mod message;
 mod unbound;
 
+/// The table of all bound netlink route sockets.
+///
+/// FIXME: NETLINK_ROUTE_SOCKET_TABLE should be a per-network namespace table
+static NETLINK_ROUTE_SOCKET_TABLE: ProtocolSocketTable<RtnlMessage> = ProtocolSocketTable::new();
+
 pub struct NetlinkRouteSocket {
     inner: RwMutex<Inner<UnboundNetlinkRoute, BoundNetlinkRoute>>,
 
     is_nonblocking: AtomicBool,
+    is_async: AtomicBool,
     pollee: Pollee,
 }
 
 impl NetlinkRouteSocket {
-    pub fn new(is_nonblocking: bool) -> Self {
-        let unbound = UnboundNetlinkRoute::new();
+    /// Creates a socket whose requests are handled in the network namespace.
+    pub fn new(is_nonblocking: bool, net_ns: Arc<NetNamespace>) -> Self {
+        let unbound = UnboundNetlinkRoute::new(net_ns);
         Self {
             inner: RwMutex::new(Inner::Unbound(unbound)),
             is_nonblocking: AtomicBool::new(is_nonblocking),
+            is_async: AtomicBool::new(false),
             pollee: Pollee::new(),
         }
     }
@@ -125,7 +137,8 @@ impl Socket for NetlinkRouteSocket {
     ) -> Result<usize> {
         let MessageHeader {
             addr,
-            control_message,
+            control_messages,
+            ..
         } = message_header;
 
         let remote = match addr {
@@ -133,7 +146,7 @@ impl Socket for NetlinkRouteSocket {
             Some(addr) => Some(addr.try_into()?),
         };
 
-        if control_message.is_some() {
+        if !control_messages.is_empty() {
             // TODO: Support sending control message
             warn!("sending control message is not supported");
         }
@@ -151,7 +164,7 @@ impl Socket for NetlinkRouteSocket {
 
         // TODO: Receive control message
 
-        let message_header = MessageHeader::new(Some(addr), None);
+        let message_header = MessageHeader::new(Some(addr), Vec::new());
 
         Ok((received_len, message_header))
     }
@@ -170,6 +183,14 @@ impl SocketPrivate for NetlinkRouteSocket {
     fn set_nonblocking(&self, nonblocking: bool) {
         self.is_nonblocking.store(nonblocking, Ordering::Relaxed);
     }
+
+    fn is_async(&self) -> bool {
+        self.is_async.load(Ordering::Relaxed)
+    }
+
+    fn set_async(&self, is_async: bool) {
+        self.is_async.store(is_async, Ordering::Relaxed);
+    }
 }
 
 impl Pollable for NetlinkRouteSocket {
@@ -1,23 +1,26 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use super::bound::BoundNetlinkRoute;
+use super::{bound::BoundNetlinkRoute, NETLINK_ROUTE_SOCKET_TABLE};
 use crate::{
     events::IoEvents,
-    net::socket::{
-        netlink::{table::NETLINK_SOCKET_TABLE, NetlinkSocketAddr, StandardNetlinkProtocol},
-        util::datagram_common,
+    net::{
+        namespace::NetNamespace,
+        socket::{
+            netlink::{table::MessageReceiver, NetlinkSocketAddr},
+            util::datagram_common,
+        },
     },
     prelude::*,
     process::signal::Pollee,
 };
 
 pub(super) struct UnboundNetlinkRoute {
-    _private: (),
+    net_ns: Arc<NetNamespace>,
 }
 
 impl UnboundNetlinkRoute {
-    pub(super) const fn new() -> Self {
-        Self { _private: () }
+    pub(super) const fn new(net_ns: Arc<NetNamespace>) -> Self {
+        Self { net_ns }
     }
 }
 
@@ -30,26 +33,27 @@ impl datagram_common::Unbound for UnboundNetlinkRoute {
     fn bind(
         &mut self,
         endpoint: &Self::Endpoint,
-        _pollee: &Pollee,
+        pollee: &Pollee,
         _options: Self::BindOptions,
     ) -> Result<BoundNetlinkRoute> {
-        let bound_handle =
-            NETLINK_SOCKET_TABLE.bind(StandardNetlinkProtocol::ROUTE as _, endpoint)?;
+        let receive_queue = Arc::new(Mutex::new(VecDeque::new()));
 
-        Ok(BoundNetlinkRoute::new(bound_handle))
+        let receiver = MessageReceiver::new(receive_queue.clone(), pollee.clone());
+        let bound_handle = NETLINK_ROUTE_SOCKET_TABLE.bind(endpoint, receiver)?;
+
+        Ok(BoundNetlinkRoute::new(
+            bound_handle,
+            receive_queue,
+            self.net_ns.clone(),
+        ))
     }
 
     fn bind_ephemeral(
         &mut self,
         _remote_endpoint: &Self::Endpoint,
-        _pollee: &Pollee,
+        pollee: &Pollee,
     ) -> Result<Self::Bound> {
-        let bound_handle = NETLINK_SOCKET_TABLE.bind(
-            StandardNetlinkProtocol::ROUTE as _,
-            &NetlinkSocketAddr::new_unspecified(),
-        )?;
-
-        Ok(BoundNetlinkRoute::new(bound_handle))
+        self.bind(&NetlinkSocketAddr::new_unspecified(), pollee, ())
     }
 
     fn check_io_events(&self) -> IoEvents {
@@ -1,80 +1,36 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use multicast::MulticastGroup;
+pub(super) use receiver::{MessageQueue, MessageReceiver};
 
 use super::addr::{GroupIdSet, NetlinkProtocolId, NetlinkSocketAddr, PortNum, MAX_GROUPS};
 use crate::{net::socket::netlink::addr::UNSPECIFIED_PORT, prelude::*, util::random::getrandom};
 
 mod multicast;
-
-pub(super) static NETLINK_SOCKET_TABLE: NetlinkSocketTable = NetlinkSocketTable::new();
-
-/// All bound netlink sockets.
-pub(super) struct NetlinkSocketTable {
-    protocols: [Mutex<Option<ProtocolSocketTable>>; MAX_ALLOWED_PROTOCOL_ID as usize],
-}
-
-impl NetlinkSocketTable {
-    pub(super) const fn new() -> Self {
-        Self {
-            protocols: [const { Mutex::new(None) }; MAX_ALLOWED_PROTOCOL_ID as usize],
-        }
-    }
-
-    /// Adds a new netlink protocol.
-    fn add_new_protocol(&self, protocol_id: NetlinkProtocolId) {
-        if protocol_id >= MAX_ALLOWED_PROTOCOL_ID {
-            return;
-        }
-
-        let mut protocol = self.protocols[protocol_id as usize].lock();
-        if protocol.is_some() {
-            return;
-        }
-
-        let new_protocol = ProtocolSocketTable::new(protocol_id);
-        *protocol = Some(new_protocol);
-    }
-
-    pub(super) fn bind(
-        &self,
-        protocol: NetlinkProtocolId,
-        addr: &NetlinkSocketAddr,
-    ) -> Result<BoundHandle> {
-        if protocol >= MAX_ALLOWED_PROTOCOL_ID {
-            return_errno_with_message!(Errno::EINVAL, "the netlink protocol does not exist");
-        }
-
-        let mut protocol = self.protocols[protocol as usize].lock();
-
-        let Some(protocol_sockets) = protocol.as_mut() else {
-            return_errno_with_message!(Errno::EINVAL, "the netlink protocol does not exist")
-        };
-
-        protocol_sockets.bind(addr)
-    }
-}
+mod receiver;
 
 /// Bound socket table of a single netlink protocol.
 ///
 /// Each table can have bound sockets for unicast
 /// and at most 32 groups for multicast.
-struct ProtocolSocketTable {
-    id: NetlinkProtocolId,
-    // TODO: This table should maintain the port number-to-socket relationship
-    // to support both unicast and multicast effectively.
-    unicast_sockets: BTreeSet<PortNum>,
-    multicast_groups: Box<[MulticastGroup]>,
+pub(super) struct ProtocolSocketTable<Message> {
+    inner: Mutex<ProtocolSocketTableInner<Message>>,
 }
 
-impl ProtocolSocketTable {
+struct ProtocolSocketTableInner<Message> {
+    unicast_sockets: BTreeMap<PortNum, MessageReceiver<Message>>,
+    multicast_groups: [MulticastGroup; MAX_GROUPS as usize],
+}
+
+impl<Message> ProtocolSocketTable<Message> {
     /// Creates a new table.
-    fn new(id: NetlinkProtocolId) -> Self {
-        let multicast_groups = (0u32..MAX_GROUPS).map(|_| MulticastGroup::new()).collect();
+    pub(super) const fn new() -> Self {
+        let inner = ProtocolSocketTableInner {
+            unicast_sockets: BTreeMap::new(),
+            multicast_groups: [const { MulticastGroup::new() }; MAX_GROUPS as usize],
+        };
         Self {
-            id,
-            unicast_sockets: BTreeSet::new(),
-            multicast_groups,
+            inner: Mutex::new(inner),
         }
     }
 
@@ -89,29 +45,60 @@ impl ProtocolSocketTable {
     ///
     /// Additionally, this socket can join one or more multicast groups,
     /// as specified in `addr.groups()`.
-    fn bind(&mut self, addr: &NetlinkSocketAddr) -> Result<BoundHandle> {
+    ///
+    /// The messages sent to the socket will be delivered by `receiver`.
+    pub(super) fn bind(
+        &'static self,
+        addr: &NetlinkSocketAddr,
+        receiver: MessageReceiver<Message>,
+    ) -> Result<BoundHandle<Message>> {
+        let mut inner = self.inner.lock();
+
         let port = if addr.port() != UNSPECIFIED_PORT {
             addr.port()
         } else {
             let mut random_port = current!().pid();
-            while random_port == UNSPECIFIED_PORT || self.unicast_sockets.contains(&random_port) {
+            while random_port == UNSPECIFIED_PORT
+                || inner.unicast_sockets.contains_key(&random_port)
+            {
                 getrandom(random_port.as_bytes_mut()).unwrap();
             }
             random_port
         };
 
-        if self.unicast_sockets.contains(&port) {
+        if inner.unicast_sockets.contains_key(&port) {
             return_errno_with_message!(Errno::EADDRINUSE, "the netlink port is already in use");
         }
 
-        self.unicast_sockets.insert(port);
+        inner.unicast_sockets.insert(port, receiver);
 
         for group_id in addr.groups().ids_iter() {
-            let group = &mut self.multicast_groups[group_id as usize];
+            let group = &mut inner.multicast_groups[group_id as usize];
             group.add_member(port);
         }
 
-        Ok(BoundHandle::new(self.id, port, addr.groups()))
+        Ok(BoundHandle::new(self, port, addr.groups()))
+    }
+}
+
+impl<Message: Clone> ProtocolSocketTable<Message> {
+    /// Sends a message to all the sockets in the multicast groups specified by `dst_groups`.
+    ///
+    /// A socket receives the message only once, even if it has joined multiple groups in
+    /// `dst_groups`.
+    pub(super) fn multicast(&self, dst_groups: GroupIdSet, message: Message) {
+        let inner = self.inner.lock();
+
+        let mut dst_ports = BTreeSet::new();
+        for group_id in dst_groups.ids_iter() {
+            let group = &inner.multicast_groups[group_id as usize];
+            dst_ports.extend(group.members());
+        }
+
+        for port in dst_ports {
+            let receiver = inner.unicast_sockets.get(&port).unwrap();
+            receiver.enqueue_message(message.clone());
+        }
     }
 }
 
@@ -119,19 +106,22 @@ impl ProtocolSocketTable {
 ///
 /// When dropping a `BoundHandle`,
 /// the port will be automatically released.
-#[derive(Debug)]
-pub(super) struct BoundHandle {
-    protocol: NetlinkProtocolId,
+pub(super) struct BoundHandle<Message> {
+    table: &'static ProtocolSocketTable<Message>,
     port: PortNum,
     groups: GroupIdSet,
 }
 
-impl BoundHandle {
-    fn new(protocol: NetlinkProtocolId, port: PortNum, groups: GroupIdSet) -> Self {
+impl<Message> BoundHandle<Message> {
+    fn new(
+        table: &'static ProtocolSocketTable<Message>,
+        port: PortNum,
+        groups: GroupIdSet,
+    ) -> Self {
         debug_assert_ne!(port, UNSPECIFIED_PORT);
 
         Self {
-            protocol,
+            table,
             port,
             groups,
         }
@@ -146,41 +136,24 @@ impl BoundHandle {
     }
 }
 
-impl Drop for BoundHandle {
+impl<Message> Drop for BoundHandle<Message> {
     fn drop(&mut self) {
-        let mut protocol_sockets = NETLINK_SOCKET_TABLE.protocols[self.protocol as usize].lock();
+        let mut inner = self.table.inner.lock();
 
-        let Some(protocol_sockets) = protocol_sockets.as_mut() else {
-            return;
-        };
-
-        protocol_sockets.unicast_sockets.remove(&self.port);
+        inner.unicast_sockets.remove(&self.port);
 
         for group_id in self.groups.ids_iter() {
-            let group = &mut protocol_sockets.multicast_groups[group_id as usize];
+            let group = &mut inner.multicast_groups[group_id as usize];
             group.remove_member(self.port);
         }
     }
 }
 
-pub(super) fn init() {
-    for protocol in 0..MAX_ALLOWED_PROTOCOL_ID {
-        if is_standard_protocol(protocol) {
-            NETLINK_SOCKET_TABLE.add_new_protocol(protocol);
-        }
-    }
-}
-
 /// Returns whether the `protocol` is valid.
 pub fn is_valid_protocol(protocol: NetlinkProtocolId) -> bool {
     protocol < MAX_ALLOWED_PROTOCOL_ID
 }
 
-/// Returns whether the `protocol` is reserved for system use.
-fn is_standard_protocol(protocol: NetlinkProtocolId) -> bool {
-    StandardNetlinkProtocol::try_from(protocol).is_ok()
-}
-
 /// Netlink protocols that are assigned for specific usage.
 ///
 /// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/netlink.h#L9>.
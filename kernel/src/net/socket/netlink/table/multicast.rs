@@ -24,6 +24,11 @@ impl MulticastGroup {
         self.members.contains(&port_num)
     }
 
+    /// Returns an iterator over the members of the multicast group.
+    pub fn members(&self) -> impl Iterator<Item = PortNum> + '_ {
+        self.members.iter().copied()
+    }
+
     /// Adds a new member to the multicast group.
     pub fn add_member(&mut self, port_num: PortNum) {
         debug_assert!(!self.members.contains(&port_num));
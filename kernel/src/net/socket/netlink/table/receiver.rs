@@ -0,0 +1,31 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{events::IoEvents, prelude::*, process::signal::Pollee};
+
+/// A queue of the messages that are sent to a netlink socket.
+pub type MessageQueue<Message> = Arc<Mutex<VecDeque<Message>>>;
+
+/// A receiver that delivers messages to a bound netlink socket.
+pub struct MessageReceiver<Message> {
+    message_queue: MessageQueue<Message>,
+    pollee: Pollee,
+}
+
+impl<Message> MessageReceiver<Message> {
+    /// Creates a new receiver that enqueues messages to `message_queue` and notifies `pollee`.
+    pub const fn new(message_queue: MessageQueue<Message>, pollee: Pollee) -> Self {
+        Self {
+            message_queue,
+            pollee,
+        }
+    }
+
+    /// Enqueues a message and notifies the socket of the incoming message.
+    pub(super) fn enqueue_message(&self, message: Message) {
+        // FIXME: Linux limits the total size of the queued messages by `SO_RCVBUF`. If the limit
+        // is exceeded, the message is dropped and the socket reports `ENOBUFS` on the next
+        // receive operation.
+        self.message_queue.lock().push_back(message);
+        self.pollee.notify(IoEvents::IN);
+    }
+}
@@ -20,4 +20,12 @@ impl_socket_options!(
     pub struct Error(Option<crate::error::Error>);
     pub struct Linger(LingerOption);
     pub struct KeepAlive(bool);
+    pub struct OobInline(bool);
+    pub struct PassCred(bool);
+    pub struct PeerCred(crate::net::socket::unix::UnixCredentials);
+    pub struct AttachBpf(Arc<crate::bpf::SocketFilter>);
+    pub struct DetachBpf(());
+    pub struct Timestamp(bool);
+    pub struct TimestampNs(bool);
+    pub struct BusyPoll(u32);
 );
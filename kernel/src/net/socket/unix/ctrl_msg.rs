@@ -0,0 +1,295 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::fmt;
+
+use crate::{
+    fs::{
+        file_handle::FileLike,
+        file_table::{get_file_fast, FdFlags, FileDesc},
+    },
+    net::socket::ControlMessage,
+    prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread, Gid, Pid, Uid},
+};
+
+/// Control messages of UNIX domain sockets.
+pub enum UnixControlMessage {
+    /// Files passed with `SCM_RIGHTS`.
+    Files(Vec<Arc<dyn FileLike>>),
+    /// Credentials passed with `SCM_CREDENTIALS`.
+    Credentials(UnixCredentials),
+}
+
+/// The types of the control messages of UNIX domain sockets.
+///
+/// The definition is from <https://elixir.bootlin.com/linux/v6.0.9/source/include/linux/socket.h#L160>.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[expect(non_camel_case_types)]
+enum CControlMessageType {
+    SCM_RIGHTS = 1,
+    SCM_CREDENTIALS = 2,
+}
+
+/// The maximum number of files that can be passed in a control message.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/net/scm.h#L22>.
+const SCM_MAX_FD: usize = 253;
+
+impl UnixControlMessage {
+    /// Parses the control message of the given type from the payload.
+    pub(in crate::net) fn read_from(type_: i32, payload: &[u8], ctx: &Context) -> Result<Self> {
+        let type_ = CControlMessageType::try_from(type_).map_err(|_| {
+            Error::with_message(Errno::EINVAL, "the control message type is not supported")
+        })?;
+
+        match type_ {
+            CControlMessageType::SCM_RIGHTS => {
+                let fds = payload.chunks_exact(size_of::<FileDesc>());
+                if fds.len() > SCM_MAX_FD {
+                    return_errno_with_message!(Errno::EINVAL, "too many files are passed");
+                }
+
+                let mut file_table = ctx.thread_local.borrow_file_table_mut();
+                let files = fds
+                    .map(|bytes| {
+                        let fd = FileDesc::from_ne_bytes(bytes.try_into().unwrap());
+                        Ok(get_file_fast!(&mut file_table, fd).into_owned())
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                Ok(Self::Files(files))
+            }
+            CControlMessageType::SCM_CREDENTIALS => {
+                if payload.len() != size_of::<UnixCredentials>() {
+                    return_errno_with_message!(
+                        Errno::EINVAL,
+                        "the length of the credentials is invalid"
+                    );
+                }
+
+                let credentials = UnixCredentials::from_bytes(payload);
+                credentials.check(ctx)?;
+
+                Ok(Self::Credentials(credentials))
+            }
+        }
+    }
+
+    /// Converts the control message to its payload with at most `max_len` bytes.
+    ///
+    /// The received files are installed to the file table of the current thread. This method
+    /// returns the type of the control message, the payload, and whether the payload is
+    /// truncated.
+    pub(in crate::net) fn write_to(
+        self,
+        max_len: usize,
+        is_cloexec: bool,
+        ctx: &Context,
+    ) -> (i32, Vec<u8>, bool) {
+        match self {
+            Self::Files(files) => {
+                let max_fds = max_len / size_of::<FileDesc>();
+                let nr_files = files.len();
+                let fd_flags = if is_cloexec {
+                    FdFlags::CLOEXEC
+                } else {
+                    FdFlags::empty()
+                };
+
+                let mut payload = Vec::new();
+                let file_table = ctx.thread_local.borrow_file_table();
+                let mut file_table_locked = file_table.unwrap().write();
+                // The files that cannot be installed are simply discarded, as Linux does.
+                for file in files.into_iter().take(max_fds) {
+                    let Ok(fd) = file_table_locked.insert(file, fd_flags) else {
+                        break;
+                    };
+                    payload.extend_from_slice(&fd.to_ne_bytes());
+                }
+
+                let is_truncated = payload.len() / size_of::<FileDesc>() < nr_files;
+                (
+                    CControlMessageType::SCM_RIGHTS as i32,
+                    payload,
+                    is_truncated,
+                )
+            }
+            Self::Credentials(credentials) => {
+                let type_ = CControlMessageType::SCM_CREDENTIALS as i32;
+                if max_len < size_of::<UnixCredentials>() {
+                    (type_, Vec::new(), true)
+                } else {
+                    (type_, credentials.as_bytes().to_vec(), false)
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for UnixControlMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Files(files) => f
+                .debug_struct("Files")
+                .field("nr_files", &files.len())
+                .finish(),
+            Self::Credentials(credentials) => {
+                f.debug_tuple("Credentials").field(credentials).finish()
+            }
+        }
+    }
+}
+
+/// The credentials of a process, i.e., `struct ucred` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod)]
+pub struct UnixCredentials {
+    pid: Pid,
+    uid: u32,
+    gid: u32,
+}
+
+impl UnixCredentials {
+    /// Returns the credentials of the current process.
+    fn new_current() -> Self {
+        let current_thread = current_thread!();
+        let posix_thread = current_thread.as_posix_thread().unwrap();
+        let credentials = posix_thread.credentials();
+
+        Self {
+            pid: posix_thread.process().pid(),
+            uid: credentials.ruid().into(),
+            gid: credentials.rgid().into(),
+        }
+    }
+
+    /// Returns the credentials of the current process that are reported to the peer via
+    /// `SO_PEERCRED`.
+    ///
+    /// Unlike the credentials in `SCM_CREDENTIALS`, these are the effective IDs. They are
+    /// recorded when the connection is established, so they will not change even if the process
+    /// later calls `setuid` or executes another program.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/core/sock.c>
+    pub(super) fn new_peer() -> Self {
+        let current_thread = current_thread!();
+        let posix_thread = current_thread.as_posix_thread().unwrap();
+        let credentials = posix_thread.credentials();
+
+        Self {
+            pid: posix_thread.process().pid(),
+            uid: credentials.euid().into(),
+            gid: credentials.egid().into(),
+        }
+    }
+
+    /// Returns the credentials that are reported via `SO_PEERCRED` if there is no peer.
+    pub(super) fn new_unknown() -> Self {
+        Self {
+            pid: 0,
+            uid: u32::MAX,
+            gid: u32::MAX,
+        }
+    }
+
+    /// Checks whether the current process is allowed to send the credentials.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/net/core/scm.c#L55>.
+    fn check(&self, ctx: &Context) -> Result<()> {
+        let credentials = ctx.posix_thread.credentials();
+
+        let uid = Uid::new(self.uid);
+        let gid = Gid::new(self.gid);
+
+        let is_pid_valid =
+            self.pid == ctx.process.pid() || credentials.has_capability(CapSet::SYS_ADMIN);
+        let is_uid_valid = uid == credentials.ruid()
+            || uid == credentials.euid()
+            || uid == credentials.suid()
+            || credentials.has_capability(CapSet::SETUID);
+        let is_gid_valid = gid == credentials.rgid()
+            || gid == credentials.egid()
+            || gid == credentials.sgid()
+            || credentials.has_capability(CapSet::SETGID);
+
+        if is_pid_valid && is_uid_valid && is_gid_valid {
+            Ok(())
+        } else {
+            return_errno_with_message!(Errno::EPERM, "the credentials cannot be sent")
+        }
+    }
+}
+
+/// The auxiliary data attached to the data sent via UNIX domain sockets.
+pub(super) struct AuxiliaryData {
+    files: Vec<Arc<dyn FileLike>>,
+    credentials: UnixCredentials,
+}
+
+impl AuxiliaryData {
+    /// Builds the auxiliary data from the control messages to send.
+    ///
+    /// If no credentials are specified, the credentials of the current process are used. This
+    /// is needed because the receiver may enable `SO_PASSCRED` after the data are sent.
+    pub(super) fn from_control_messages(control_messages: Vec<ControlMessage>) -> Self {
+        let mut files = Vec::new();
+        let mut credentials = None;
+
+        for control_message in control_messages {
+            let ControlMessage::Unix(unix_message) = control_message else {
+                continue;
+            };
+            match unix_message {
+                UnixControlMessage::Files(mut new_files) => files.append(&mut new_files),
+                UnixControlMessage::Credentials(new_credentials) => {
+                    credentials = Some(new_credentials)
+                }
+            }
+        }
+
+        Self {
+            files,
+            credentials: credentials.unwrap_or_else(UnixCredentials::new_current),
+        }
+    }
+
+    /// Converts the auxiliary data to the control messages to receive.
+    ///
+    /// The credentials are only received if `SO_PASSCRED` is enabled on the receiver.
+    pub(super) fn into_control_messages(self, is_pass_cred: bool) -> Vec<ControlMessage> {
+        let mut control_messages = Vec::new();
+
+        if is_pass_cred {
+            control_messages.push(ControlMessage::Unix(UnixControlMessage::Credentials(
+                self.credentials,
+            )));
+        }
+        if !self.files.is_empty() {
+            control_messages.push(ControlMessage::Unix(UnixControlMessage::Files(self.files)));
+        }
+
+        control_messages
+    }
+
+    /// Returns whether the auxiliary data contain any files.
+    pub(super) fn has_files(&self) -> bool {
+        !self.files.is_empty()
+    }
+
+    /// Returns the credentials of the sender.
+    pub(super) fn credentials(&self) -> &UnixCredentials {
+        &self.credentials
+    }
+
+    /// Takes the auxiliary data out, leaving only the credentials behind.
+    ///
+    /// This is used when the data are partially read. The files are received with the first
+    /// part of the data, while the credentials are received with every part of the data.
+    pub(super) fn take(&mut self) -> Self {
+        Self {
+            files: core::mem::take(&mut self.files),
+            credentials: self.credentials,
+        }
+    }
+}
@@ -0,0 +1,211 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use ostd::sync::WaitQueue;
+
+use crate::{
+    events::IoEvents,
+    net::socket::{
+        unix::{addr::UnixSocketAddrKey, ctrl_msg::AuxiliaryData, UnixSocketAddr},
+        SockShutdownCmd,
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollee},
+};
+
+/// A datagram sent via UNIX domain sockets.
+pub(super) struct Message {
+    pub(super) src: UnixSocketAddr,
+    pub(super) data: Box<[u8]>,
+    pub(super) aux_data: AuxiliaryData,
+}
+
+/// The queue of the datagrams received by a UNIX datagram socket.
+pub(super) struct MessageQueue {
+    inner: SpinLock<Inner>,
+    pollee: Pollee,
+    /// The wait queue for the senders that are waiting for free space.
+    wait_queue: WaitQueue,
+}
+
+struct Inner {
+    messages: VecDeque<Message>,
+    total_len: usize,
+    is_read_shutdown: bool,
+    is_write_shutdown: bool,
+    /// Whether the receiving socket has been closed.
+    is_closed: bool,
+}
+
+impl MessageQueue {
+    pub(super) fn new() -> Self {
+        Self {
+            inner: SpinLock::new(Inner {
+                messages: VecDeque::new(),
+                total_len: 0,
+                is_read_shutdown: false,
+                is_write_shutdown: false,
+                is_closed: false,
+            }),
+            pollee: Pollee::new(),
+            wait_queue: WaitQueue::new(),
+        }
+    }
+
+    /// Tries to push the message to the queue.
+    ///
+    /// If the message is pushed successfully, it will be taken out of `message`.
+    pub(super) fn try_push(&self, message: &mut Option<Message>) -> Result<()> {
+        let mut inner = self.inner.lock();
+
+        if inner.is_closed {
+            return_errno_with_message!(Errno::ECONNREFUSED, "the receiving socket is closed");
+        }
+        if inner.is_read_shutdown {
+            return_errno_with_message!(
+                Errno::EPIPE,
+                "the receiving socket is shut down for reading"
+            );
+        }
+
+        let len = message.as_ref().unwrap().data.len();
+        // A message is always accepted if the queue is empty, so that the sender will not wait
+        // forever.
+        if inner.total_len + len > MAX_QUEUE_LEN && !inner.messages.is_empty() {
+            return_errno_with_message!(Errno::EAGAIN, "the receiving queue is full");
+        }
+
+        inner.messages.push_back(message.take().unwrap());
+        inner.total_len += len;
+        self.pollee.notify(IoEvents::IN);
+
+        Ok(())
+    }
+
+    /// Tries to pop a message from the queue.
+    ///
+    /// - Returns `Ok(Some(_))` with the popped message if successful.
+    /// - Returns `Ok(None)` if the queue is shut down for reading and there is no message left.
+    /// - Returns `Err(EAGAIN)` if the queue is empty.
+    pub(super) fn try_pop(&self) -> Result<Option<Message>> {
+        let mut inner = self.inner.lock();
+
+        let Some(message) = inner.messages.pop_front() else {
+            if inner.is_read_shutdown {
+                return Ok(None);
+            }
+            return_errno_with_message!(Errno::EAGAIN, "the receiving queue is empty");
+        };
+        inner.total_len -= message.data.len();
+
+        drop(inner);
+
+        self.pollee.invalidate();
+        self.wait_queue.wake_all();
+
+        Ok(Some(message))
+    }
+
+    /// Waits until the condition is met.
+    ///
+    /// The condition is checked again whenever a message is popped from the queue or the queue
+    /// is closed.
+    pub(super) fn pause_until<F>(&self, mut cond: F) -> Result<()>
+    where
+        F: FnMut() -> Result<()>,
+    {
+        self.wait_queue.pause_until(|| match cond() {
+            Err(err) if err.error() == Errno::EAGAIN => None,
+            result => Some(result),
+        })?
+    }
+
+    pub(super) fn is_write_shutdown(&self) -> bool {
+        self.inner.lock().is_write_shutdown
+    }
+
+    pub(super) fn shutdown(&self, cmd: SockShutdownCmd) {
+        let mut inner = self.inner.lock();
+
+        if cmd.shut_read() {
+            inner.is_read_shutdown = true;
+        }
+        if cmd.shut_write() {
+            inner.is_write_shutdown = true;
+        }
+
+        drop(inner);
+
+        self.pollee
+            .notify(IoEvents::IN | IoEvents::RDHUP | IoEvents::HUP);
+        self.wait_queue.wake_all();
+    }
+
+    /// Closes the queue when the receiving socket is closed.
+    ///
+    /// The pending messages are discarded, along with the files in them.
+    pub(super) fn close(&self) {
+        let mut inner = self.inner.lock();
+
+        inner.is_closed = true;
+        let messages = core::mem::take(&mut inner.messages);
+        inner.total_len = 0;
+
+        drop(inner);
+
+        // The messages are dropped without holding the lock, because dropping the files in them
+        // may sleep.
+        drop(messages);
+        self.wait_queue.wake_all();
+    }
+
+    pub(super) fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.pollee
+            .poll_with(mask, poller, || self.check_io_events())
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        let inner = self.inner.lock();
+
+        // TODO: Report whether the receiving queue of the peer is full. Currently, the socket is
+        // always writable.
+        let mut events = IoEvents::OUT;
+
+        if !inner.messages.is_empty() {
+            events |= IoEvents::IN;
+        }
+        if inner.is_read_shutdown {
+            events |= IoEvents::IN | IoEvents::RDHUP;
+
+            if inner.is_write_shutdown {
+                events |= IoEvents::HUP;
+            }
+        }
+
+        events
+    }
+}
+
+/// The maximum total length of the messages in a queue.
+///
+/// This is also the maximum length of a single message.
+pub(super) const MAX_QUEUE_LEN: usize = 65536;
+
+static QUEUE_TABLE: RwLock<BTreeMap<UnixSocketAddrKey, Arc<MessageQueue>>> =
+    RwLock::new(BTreeMap::new());
+
+pub(super) fn register_queue(addr: UnixSocketAddrKey, queue: Arc<MessageQueue>) {
+    QUEUE_TABLE.write().insert(addr, queue);
+}
+
+pub(super) fn unregister_queue(addr: &UnixSocketAddrKey) {
+    QUEUE_TABLE.write().remove(addr);
+}
+
+pub(super) fn get_queue(addr: &UnixSocketAddrKey) -> Result<Arc<MessageQueue>> {
+    QUEUE_TABLE.read().get(addr).cloned().ok_or_else(|| {
+        Error::with_message(
+            Errno::ECONNREFUSED,
+            "no datagram socket is bound to the remote address",
+        )
+    })
+}
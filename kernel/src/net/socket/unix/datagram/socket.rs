@@ -0,0 +1,268 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use super::message::{
+    get_queue, register_queue, unregister_queue, Message, MessageQueue, MAX_QUEUE_LEN,
+};
+use crate::{
+    events::IoEvents,
+    match_sock_option_mut, match_sock_option_ref,
+    net::socket::{
+        options::{PassCred, SocketOption},
+        private::SocketPrivate,
+        unix::{addr::UnixSocketAddrBound, ctrl_msg::AuxiliaryData, UnixSocketAddr},
+        util::{send_recv_flags::SendRecvFlags, socket_addr::SocketAddr, MessageHeader},
+        SockShutdownCmd, Socket,
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+    util::{MultiRead, MultiWrite},
+};
+
+pub struct UnixDatagramSocket {
+    addr: Mutex<Option<UnixSocketAddrBound>>,
+    peer: SpinLock<Option<Peer>>,
+    queue: Arc<MessageQueue>,
+    is_nonblocking: AtomicBool,
+    is_async: AtomicBool,
+    is_pass_cred: AtomicBool,
+}
+
+/// The default destination of a connected datagram socket.
+struct Peer {
+    addr: UnixSocketAddr,
+    queue: Arc<MessageQueue>,
+}
+
+impl UnixDatagramSocket {
+    pub fn new(is_nonblocking: bool) -> Arc<Self> {
+        Self::new_with_queue(Arc::new(MessageQueue::new()), None, is_nonblocking)
+    }
+
+    pub fn new_pair(is_nonblocking: bool) -> (Arc<Self>, Arc<Self>) {
+        let queue_a = Arc::new(MessageQueue::new());
+        let queue_b = Arc::new(MessageQueue::new());
+
+        let peer_a = Peer {
+            addr: UnixSocketAddr::Unnamed,
+            queue: queue_b.clone(),
+        };
+        let peer_b = Peer {
+            addr: UnixSocketAddr::Unnamed,
+            queue: queue_a.clone(),
+        };
+
+        (
+            Self::new_with_queue(queue_a, Some(peer_a), is_nonblocking),
+            Self::new_with_queue(queue_b, Some(peer_b), is_nonblocking),
+        )
+    }
+
+    fn new_with_queue(
+        queue: Arc<MessageQueue>,
+        peer: Option<Peer>,
+        is_nonblocking: bool,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            addr: Mutex::new(None),
+            peer: SpinLock::new(peer),
+            queue,
+            is_nonblocking: AtomicBool::new(is_nonblocking),
+            is_async: AtomicBool::new(false),
+            is_pass_cred: AtomicBool::new(false),
+        })
+    }
+
+    fn try_recv(&self, writer: &mut dyn MultiWrite) -> Result<(usize, Option<Message>)> {
+        let Some(message) = self.queue.try_pop()? else {
+            return Ok((0, None));
+        };
+
+        // The rest of the message is discarded if it does not fit into the buffer.
+        let received_len = writer.write(&mut VmReader::from(message.data.as_ref()))?;
+
+        Ok((received_len, Some(message)))
+    }
+}
+
+impl Pollable for UnixDatagramSocket {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.queue.poll(mask, poller)
+    }
+}
+
+impl SocketPrivate for UnixDatagramSocket {
+    fn is_nonblocking(&self) -> bool {
+        self.is_nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) {
+        self.is_nonblocking.store(nonblocking, Ordering::Relaxed);
+    }
+
+    fn is_async(&self) -> bool {
+        self.is_async.load(Ordering::Relaxed)
+    }
+
+    fn set_async(&self, is_async: bool) {
+        self.is_async.store(is_async, Ordering::Relaxed);
+    }
+}
+
+impl Socket for UnixDatagramSocket {
+    fn bind(&self, socket_addr: SocketAddr) -> Result<()> {
+        let addr_to_bind = UnixSocketAddr::try_from(socket_addr)?;
+
+        let mut addr = self.addr.lock();
+
+        if addr.is_some() {
+            return addr_to_bind.bind_unnamed();
+        }
+
+        let bound_addr = addr_to_bind.bind()?;
+        register_queue(bound_addr.to_key(), self.queue.clone());
+        *addr = Some(bound_addr);
+
+        Ok(())
+    }
+
+    fn connect(&self, socket_addr: SocketAddr) -> Result<()> {
+        let peer_addr = UnixSocketAddr::try_from(socket_addr)?;
+        let queue = get_queue(&peer_addr.connect()?)?;
+
+        *self.peer.lock() = Some(Peer {
+            addr: peer_addr,
+            queue,
+        });
+
+        Ok(())
+    }
+
+    fn shutdown(&self, cmd: SockShutdownCmd) -> Result<()> {
+        self.queue.shutdown(cmd);
+
+        Ok(())
+    }
+
+    fn addr(&self) -> Result<SocketAddr> {
+        Ok(self.addr.lock().clone().into())
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr> {
+        match self.peer.lock().as_ref() {
+            Some(peer) => Ok(peer.addr.clone().into()),
+            None => return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected"),
+        }
+    }
+
+    fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
+        match_sock_option_mut!(option, {
+            socket_pass_cred: PassCred => {
+                socket_pass_cred.set(self.is_pass_cred.load(Ordering::Relaxed));
+            },
+            _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to get is unknown")
+        });
+        Ok(())
+    }
+
+    fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        match_sock_option_ref!(option, {
+            socket_pass_cred: PassCred => {
+                let pass_cred = socket_pass_cred.get().unwrap();
+                self.is_pass_cred.store(*pass_cred, Ordering::Relaxed);
+            },
+            _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to be set is unknown")
+        });
+        Ok(())
+    }
+
+    fn sendmsg(
+        &self,
+        reader: &mut dyn MultiRead,
+        message_header: MessageHeader,
+        flags: SendRecvFlags,
+    ) -> Result<usize> {
+        // TODO: Deal with flags
+        if !flags.is_all_supported() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let MessageHeader {
+            addr,
+            control_messages,
+            ..
+        } = message_header;
+
+        let queue = match addr {
+            Some(addr) => get_queue(&UnixSocketAddr::try_from(addr)?.connect()?)?,
+            None => match self.peer.lock().as_ref() {
+                Some(peer) => peer.queue.clone(),
+                None => {
+                    return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected")
+                }
+            },
+        };
+
+        if self.queue.is_write_shutdown() {
+            return_errno_with_message!(Errno::EPIPE, "the socket is shut down for writing");
+        }
+
+        let len = reader.sum_lens();
+        if len > MAX_QUEUE_LEN {
+            return_errno_with_message!(Errno::EMSGSIZE, "the message is too large");
+        }
+
+        let mut data = vec![0u8; len].into_boxed_slice();
+        reader.read(&mut VmWriter::from(data.as_mut()))?;
+
+        let mut message = Some(Message {
+            src: self.addr.lock().clone().into(),
+            data,
+            aux_data: AuxiliaryData::from_control_messages(control_messages),
+        });
+
+        if self.is_nonblocking() {
+            queue.try_push(&mut message)?;
+        } else {
+            queue.pause_until(|| queue.try_push(&mut message))?;
+        }
+
+        Ok(len)
+    }
+
+    fn recvmsg(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, MessageHeader)> {
+        // TODO: Deal with flags
+        if !flags.is_all_supported() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let (received_len, message) = self.block_on(IoEvents::IN, || self.try_recv(writer))?;
+
+        let message_header = match message {
+            Some(message) => MessageHeader::new(
+                Some(message.src.into()),
+                message
+                    .aux_data
+                    .into_control_messages(self.is_pass_cred.load(Ordering::Relaxed)),
+            ),
+            None => MessageHeader::new(None, Vec::new()),
+        };
+
+        Ok((received_len, message_header))
+    }
+}
+
+impl Drop for UnixDatagramSocket {
+    fn drop(&mut self) {
+        if let Some(addr) = self.addr.get_mut().as_ref() {
+            unregister_queue(&addr.to_key());
+        }
+
+        self.queue.close();
+    }
+}
@@ -1,8 +1,12 @@
 // SPDX-License-Identifier: MPL-2.0
 
 mod addr;
+mod ctrl_msg;
+mod datagram;
 mod ns;
 mod stream;
 
 pub use addr::UnixSocketAddr;
+pub use ctrl_msg::{UnixControlMessage, UnixCredentials};
+pub use datagram::UnixDatagramSocket;
 pub use stream::UnixStreamSocket;
@@ -14,7 +14,8 @@ pub fn lookup_socket_file(path: &str) -> Result<Dentry> {
     let dentry = {
         let current = current_thread!();
         let current = current.as_posix_thread().unwrap();
-        let fs = current.fs().resolver().read();
+        let fs_ref = current.fs().read();
+        let fs = fs_ref.resolver().read();
         let fs_path = FsPath::try_from(path)?;
         fs.lookup(&fs_path)?
     };
@@ -43,7 +44,8 @@ pub fn create_socket_file(path: &str) -> Result<Dentry> {
     let parent = {
         let current = current_thread!();
         let current = current.as_posix_thread().unwrap();
-        let fs = current.fs().resolver().read();
+        let fs_ref = current.fs().read();
+        let fs = fs_ref.resolver().read();
         let parent_path = FsPath::try_from(parent_pathname)?;
         fs.lookup(&parent_path)?
     };
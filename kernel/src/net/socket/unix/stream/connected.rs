@@ -8,7 +8,9 @@ use crate::{
     events::IoEvents,
     fs::utils::{Channel, Consumer, Producer},
     net::socket::{
-        unix::{addr::UnixSocketAddrBound, UnixSocketAddr},
+        unix::{
+            addr::UnixSocketAddrBound, ctrl_msg::AuxiliaryData, UnixCredentials, UnixSocketAddr,
+        },
         SockShutdownCmd,
     },
     prelude::*,
@@ -18,14 +20,23 @@ use crate::{
 
 pub(super) struct Connected {
     addr: AddrView,
+    peer_cred: UnixCredentials,
     reader: Consumer<u8>,
     writer: Producer<u8>,
+    reader_aux: Arc<Mutex<AuxQueue>>,
+    writer_aux: Arc<Mutex<AuxQueue>>,
 }
 
 impl Connected {
+    /// Creates a pair of connected sockets.
+    ///
+    /// The credentials are those of the owners of the two sockets, which are reported to the
+    /// other side via `SO_PEERCRED`.
     pub(super) fn new_pair(
         addr: Option<UnixSocketAddrBound>,
         peer_addr: Option<UnixSocketAddrBound>,
+        cred: UnixCredentials,
+        peer_cred: UnixCredentials,
         reader_pollee: Option<Pollee>,
         writer_pollee: Option<Pollee>,
     ) -> (Connected, Connected) {
@@ -36,15 +47,24 @@ impl Connected {
 
         let (addr_this, addr_peer) = AddrView::new_pair(addr, peer_addr);
 
+        let aux_this = Arc::new(Mutex::new(AuxQueue::new()));
+        let aux_peer = Arc::new(Mutex::new(AuxQueue::new()));
+
         let this = Connected {
             addr: addr_this,
+            peer_cred,
             reader: reader_this,
             writer: writer_this,
+            reader_aux: aux_this.clone(),
+            writer_aux: aux_peer.clone(),
         };
         let peer = Connected {
             addr: addr_peer,
+            peer_cred: cred,
             reader: reader_peer,
             writer: writer_peer,
+            reader_aux: aux_peer,
+            writer_aux: aux_this,
         };
 
         (this, peer)
@@ -58,6 +78,10 @@ impl Connected {
         self.addr.peer_addr()
     }
 
+    pub(super) fn peer_cred(&self) -> UnixCredentials {
+        self.peer_cred
+    }
+
     pub(super) fn bind(&self, addr_to_bind: UnixSocketAddr) -> Result<()> {
         let mut addr = self.addr.addr();
 
@@ -71,12 +95,60 @@ impl Connected {
         Ok(())
     }
 
-    pub(super) fn try_read(&self, writer: &mut dyn MultiWrite) -> Result<usize> {
-        self.reader.try_read(writer)
+    pub(super) fn try_read(
+        &self,
+        writer: &mut dyn MultiWrite,
+    ) -> Result<(usize, Option<AuxiliaryData>)> {
+        let mut aux_queue = self.reader_aux.lock();
+
+        // The data with different auxiliary data cannot be read at once.
+        let Some(segment) = aux_queue.segments.front_mut() else {
+            return Ok((self.reader.try_read(writer)?, None));
+        };
+
+        let read_len = self.reader.try_read_with_max_len(writer, segment.len)?;
+        if read_len == 0 {
+            return Ok((0, None));
+        }
+
+        let aux_data = segment.aux_data.take();
+        segment.len -= read_len;
+        if segment.len == 0 {
+            aux_queue.segments.pop_front();
+        }
+
+        Ok((read_len, Some(aux_data)))
     }
 
-    pub(super) fn try_write(&self, reader: &mut dyn MultiRead) -> Result<usize> {
-        self.writer.try_write(reader)
+    pub(super) fn try_write(
+        &self,
+        reader: &mut dyn MultiRead,
+        aux_data: &mut Option<AuxiliaryData>,
+    ) -> Result<usize> {
+        let mut aux_queue = self.writer_aux.lock();
+
+        let written_len = self.writer.try_write(reader)?;
+        if written_len == 0 {
+            return Ok(0);
+        }
+
+        let aux_data = aux_data.take().unwrap();
+        match aux_queue.segments.back_mut() {
+            // Merge the segments if they can be read at once.
+            Some(segment)
+                if !segment.aux_data.has_files()
+                    && !aux_data.has_files()
+                    && segment.aux_data.credentials() == aux_data.credentials() =>
+            {
+                segment.len += written_len;
+            }
+            _ => aux_queue.segments.push_back(AuxSegment {
+                len: written_len,
+                aux_data,
+            }),
+        }
+
+        Ok(written_len)
     }
 
     pub(super) fn shutdown(&self, cmd: SockShutdownCmd) {
@@ -156,4 +228,26 @@ impl AddrView {
     }
 }
 
+/// The auxiliary data of the bytes in a channel.
+///
+/// The bytes that have not been read are divided into segments, each of which has its own
+/// auxiliary data. The segments are ordered and their total length is always equal to the number
+/// of bytes in the channel.
+struct AuxQueue {
+    segments: VecDeque<AuxSegment>,
+}
+
+struct AuxSegment {
+    len: usize,
+    aux_data: AuxiliaryData,
+}
+
+impl AuxQueue {
+    fn new() -> Self {
+        Self {
+            segments: VecDeque::new(),
+        }
+    }
+}
+
 const DEFAULT_BUF_SIZE: usize = 65536;
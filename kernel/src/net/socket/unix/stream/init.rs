@@ -9,7 +9,10 @@ use super::{
 use crate::{
     events::IoEvents,
     net::socket::{
-        unix::addr::{UnixSocketAddr, UnixSocketAddrBound},
+        unix::{
+            addr::{UnixSocketAddr, UnixSocketAddrBound},
+            UnixCredentials,
+        },
         SockShutdownCmd,
     },
     prelude::*,
@@ -46,7 +49,11 @@ impl Init {
         Ok(())
     }
 
-    pub(super) fn into_connected(self, peer_addr: UnixSocketAddrBound) -> (Connected, Connected) {
+    pub(super) fn into_connected(
+        self,
+        peer_addr: UnixSocketAddrBound,
+        peer_cred: UnixCredentials,
+    ) -> (Connected, Connected) {
         let Init {
             addr,
             reader_pollee,
@@ -58,6 +65,8 @@ impl Init {
         let (this_conn, peer_conn) = Connected::new_pair(
             addr,
             Some(peer_addr),
+            UnixCredentials::new_peer(),
+            peer_cred,
             Some(reader_pollee),
             Some(writer_pollee),
         );
@@ -13,7 +13,10 @@ use crate::{
     events::IoEvents,
     fs::file_handle::FileLike,
     net::socket::{
-        unix::addr::{UnixSocketAddrBound, UnixSocketAddrKey},
+        unix::{
+            addr::{UnixSocketAddrBound, UnixSocketAddrKey},
+            UnixCredentials,
+        },
         SockShutdownCmd, SocketAddr,
     },
     prelude::*,
@@ -51,11 +54,19 @@ impl Listener {
         self.backlog.addr()
     }
 
-    pub(super) fn try_accept(&self) -> Result<(Arc<dyn FileLike>, SocketAddr)> {
+    /// Returns the credentials of the owner of the listening socket.
+    ///
+    /// Like Linux, these are the credentials when the socket starts listening.
+    pub(super) fn cred(&self) -> UnixCredentials {
+        self.backlog.cred
+    }
+
+    pub(super) fn try_accept(&self, is_pass_cred: bool) -> Result<(Arc<dyn FileLike>, SocketAddr)> {
         let connected = self.backlog.pop_incoming()?;
         let peer_addr = connected.peer_addr().into();
 
-        let socket = UnixStreamSocket::new_connected(connected, false);
+        // The accepted socket inherits `SO_PASSCRED` from the listening socket.
+        let socket = UnixStreamSocket::new_connected(connected, false, is_pass_cred);
         Ok((socket, peer_addr))
     }
 
@@ -150,6 +161,7 @@ impl BacklogTable {
 
 pub(super) struct Backlog {
     addr: UnixSocketAddrBound,
+    cred: UnixCredentials,
     pollee: Pollee,
     backlog: AtomicUsize,
     incoming_conns: SpinLock<Option<VecDeque<Connected>>>,
@@ -166,6 +178,7 @@ impl Backlog {
 
         Self {
             addr,
+            cred: UnixCredentials::new_peer(),
             pollee,
             backlog: AtomicUsize::new(backlog),
             incoming_conns: SpinLock::new(incoming_sockets),
@@ -260,7 +273,7 @@ impl Backlog {
             ));
         }
 
-        let (client_conn, server_conn) = init.into_connected(self.addr.clone());
+        let (client_conn, server_conn) = init.into_connected(self.addr.clone(), self.cred);
 
         incoming_conns.push_back(server_conn);
         self.pollee.notify(IoEvents::IN);
@@ -12,11 +12,19 @@ use super::{
 use crate::{
     events::IoEvents,
     fs::file_handle::FileLike,
-    net::socket::{
-        private::SocketPrivate,
-        unix::UnixSocketAddr,
-        util::{send_recv_flags::SendRecvFlags, socket_addr::SocketAddr, MessageHeader},
-        SockShutdownCmd, Socket,
+    match_sock_option_mut, match_sock_option_ref,
+    net::{
+        socket::{
+            options::{PassCred, PeerCred, SocketOption},
+            private::SocketPrivate,
+            unix::{ctrl_msg::AuxiliaryData, UnixCredentials, UnixSocketAddr},
+            util::{
+                send_recv_flags::SendRecvFlags, send_sigpipe_on_epipe, socket_addr::SocketAddr,
+                MessageHeader,
+            },
+            SockShutdownCmd, Socket,
+        },
+        sysctl,
     },
     prelude::*,
     process::signal::{PollHandle, Pollable},
@@ -26,6 +34,8 @@ use crate::{
 pub struct UnixStreamSocket {
     state: RwMutex<Takeable<State>>,
     is_nonblocking: AtomicBool,
+    is_async: AtomicBool,
+    is_pass_cred: AtomicBool,
 }
 
 impl UnixStreamSocket {
@@ -33,13 +43,21 @@ impl UnixStreamSocket {
         Arc::new(Self {
             state: RwMutex::new(Takeable::new(State::Init(init))),
             is_nonblocking: AtomicBool::new(is_nonblocking),
+            is_async: AtomicBool::new(false),
+            is_pass_cred: AtomicBool::new(false),
         })
     }
 
-    pub(super) fn new_connected(connected: Connected, is_nonblocking: bool) -> Arc<Self> {
+    pub(super) fn new_connected(
+        connected: Connected,
+        is_nonblocking: bool,
+        is_pass_cred: bool,
+    ) -> Arc<Self> {
         Arc::new(Self {
             state: RwMutex::new(Takeable::new(State::Connected(connected))),
             is_nonblocking: AtomicBool::new(is_nonblocking),
+            is_async: AtomicBool::new(false),
+            is_pass_cred: AtomicBool::new(is_pass_cred),
         })
     }
 }
@@ -56,23 +74,41 @@ impl UnixStreamSocket {
     }
 
     pub fn new_pair(is_nonblocking: bool) -> (Arc<Self>, Arc<Self>) {
-        let (conn_a, conn_b) = Connected::new_pair(None, None, None, None);
+        let cred = UnixCredentials::new_peer();
+        let (conn_a, conn_b) = Connected::new_pair(None, None, cred, cred, None, None);
         (
-            Self::new_connected(conn_a, is_nonblocking),
-            Self::new_connected(conn_b, is_nonblocking),
+            Self::new_connected(conn_a, is_nonblocking, false),
+            Self::new_connected(conn_b, is_nonblocking, false),
         )
     }
 
-    fn try_send(&self, buf: &mut dyn MultiRead, _flags: SendRecvFlags) -> Result<usize> {
+    fn peer_cred(&self) -> UnixCredentials {
         match self.state.read().as_ref() {
-            State::Connected(connected) => connected.try_write(buf),
+            State::Init(_) => UnixCredentials::new_unknown(),
+            State::Listen(listen) => listen.cred(),
+            State::Connected(connected) => connected.peer_cred(),
+        }
+    }
+
+    fn try_send(
+        &self,
+        buf: &mut dyn MultiRead,
+        aux_data: &mut Option<AuxiliaryData>,
+        _flags: SendRecvFlags,
+    ) -> Result<usize> {
+        match self.state.read().as_ref() {
+            State::Connected(connected) => connected.try_write(buf, aux_data),
             State::Init(_) | State::Listen(_) => {
                 return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected")
             }
         }
     }
 
-    fn try_recv(&self, buf: &mut dyn MultiWrite, _flags: SendRecvFlags) -> Result<usize> {
+    fn try_recv(
+        &self,
+        buf: &mut dyn MultiWrite,
+        _flags: SendRecvFlags,
+    ) -> Result<(usize, Option<AuxiliaryData>)> {
         match self.state.read().as_ref() {
             State::Connected(connected) => connected.try_read(buf),
             State::Init(_) | State::Listen(_) => {
@@ -118,7 +154,9 @@ impl UnixStreamSocket {
 
     fn try_accept(&self) -> Result<(Arc<dyn FileLike>, SocketAddr)> {
         match self.state.read().as_ref() {
-            State::Listen(listen) => listen.try_accept() as _,
+            State::Listen(listen) => {
+                listen.try_accept(self.is_pass_cred.load(Ordering::Relaxed)) as _
+            }
             State::Init(_) | State::Connected(_) => {
                 return_errno_with_message!(Errno::EINVAL, "the socket is not listening")
             }
@@ -145,6 +183,14 @@ impl SocketPrivate for UnixStreamSocket {
     fn set_nonblocking(&self, nonblocking: bool) {
         self.is_nonblocking.store(nonblocking, Ordering::Relaxed);
     }
+
+    fn is_async(&self) -> bool {
+        self.is_async.load(Ordering::Relaxed)
+    }
+
+    fn set_async(&self, is_async: bool) {
+        self.is_async.store(is_async, Ordering::Relaxed);
+    }
 }
 
 impl Socket for UnixStreamSocket {
@@ -173,12 +219,10 @@ impl Socket for UnixStreamSocket {
     }
 
     fn listen(&self, backlog: usize) -> Result<()> {
-        const SOMAXCONN: usize = 4096;
-
         // Linux allows a maximum of `backlog + 1` sockets in the backlog queue. Although this
         // seems to be mostly an implementation detail, we follow the exact Linux behavior to
         // ensure that our regression tests pass with the Linux kernel.
-        let backlog = backlog.saturating_add(1).min(SOMAXCONN);
+        let backlog = backlog.min(sysctl::somaxconn()).saturating_add(1);
 
         let mut state = self.state.write();
 
@@ -212,7 +256,7 @@ impl Socket for UnixStreamSocket {
     }
 
     fn accept(&self) -> Result<(Arc<dyn FileLike>, SocketAddr)> {
-        self.block_on(IoEvents::IN, || self.try_accept())
+        self.block_on_exclusive(IoEvents::IN, || self.try_accept())
     }
 
     fn shutdown(&self, cmd: SockShutdownCmd) -> Result<()> {
@@ -246,6 +290,30 @@ impl Socket for UnixStreamSocket {
         Ok(peer_addr.into())
     }
 
+    fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
+        match_sock_option_mut!(option, {
+            socket_pass_cred: PassCred => {
+                socket_pass_cred.set(self.is_pass_cred.load(Ordering::Relaxed));
+            },
+            socket_peer_cred: PeerCred => {
+                socket_peer_cred.set(self.peer_cred());
+            },
+            _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to get is unknown")
+        });
+        Ok(())
+    }
+
+    fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        match_sock_option_ref!(option, {
+            socket_pass_cred: PassCred => {
+                let pass_cred = socket_pass_cred.get().unwrap();
+                self.is_pass_cred.store(*pass_cred, Ordering::Relaxed);
+            },
+            _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to be set is unknown")
+        });
+        Ok(())
+    }
+
     fn sendmsg(
         &self,
         reader: &mut dyn MultiRead,
@@ -258,15 +326,16 @@ impl Socket for UnixStreamSocket {
         }
 
         let MessageHeader {
-            control_message, ..
+            control_messages, ..
         } = message_header;
 
-        if control_message.is_some() {
-            // TODO: Support sending control message
-            warn!("sending control message is not supported");
-        }
+        let mut aux_data = Some(AuxiliaryData::from_control_messages(control_messages));
 
-        self.block_on(IoEvents::OUT, || self.try_send(reader, flags))
+        let res = self.block_on(IoEvents::OUT, || {
+            self.try_send(reader, &mut aux_data, flags)
+        });
+        send_sigpipe_on_epipe(&res, flags);
+        res
     }
 
     fn recvmsg(
@@ -279,11 +348,15 @@ impl Socket for UnixStreamSocket {
             warn!("unsupported flags: {:?}", flags);
         }
 
-        let received_bytes = self.block_on(IoEvents::IN, || self.try_recv(writer, flags))?;
-
-        // TODO: Receive control message
+        let (received_bytes, aux_data) =
+            self.block_on(IoEvents::IN, || self.try_recv(writer, flags))?;
 
-        let message_header = MessageHeader::new(None, None);
+        let control_messages = aux_data
+            .map(|aux_data| {
+                aux_data.into_control_messages(self.is_pass_cred.load(Ordering::Relaxed))
+            })
+            .unwrap_or_default();
+        let message_header = MessageHeader::new(None, control_messages);
 
         Ok((received_bytes, message_header))
     }
@@ -0,0 +1,136 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use align_ext::AlignExt;
+
+use crate::{
+    net::socket::{ip::IpControlMessage, unix::UnixControlMessage},
+    prelude::*,
+    util::net::CSocketOptionLevel,
+};
+
+/// Control message (also known as ancillary data) carried by [`MessageHeader`].
+///
+/// [`MessageHeader`]: super::MessageHeader
+#[derive(Debug)]
+pub enum ControlMessage {
+    Unix(UnixControlMessage),
+    Ip(IpControlMessage),
+}
+
+/// The header of a control message.
+///
+/// The definition is from <https://elixir.bootlin.com/linux/v6.0.9/source/include/linux/socket.h#L105>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CControlMessageHeader {
+    /// The length of the header and the payload (excluding the trailing padding)
+    cmsg_len: usize,
+    cmsg_level: i32,
+    cmsg_type: i32,
+}
+
+const HEADER_LEN: usize = size_of::<CControlMessageHeader>();
+
+/// The alignment of control messages, i.e., `CMSG_ALIGN` in Linux.
+const CMSG_ALIGN: usize = align_of::<CControlMessageHeader>();
+
+/// The maximum length of the control messages that can be sent at a time.
+///
+/// This is the default value of `net.core.optmem_max` in Linux.
+const MAX_SEND_LEN: usize = 20480;
+
+impl ControlMessage {
+    /// Reads all the control messages from the user space.
+    pub fn read_all_from_user(addr: Vaddr, len: usize, ctx: &Context) -> Result<Vec<Self>> {
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+        if len > MAX_SEND_LEN {
+            return_errno_with_message!(Errno::ENOBUFS, "the control messages are too long");
+        }
+
+        let mut buf = vec![0u8; len];
+        ctx.user_space()
+            .read_bytes(addr, &mut VmWriter::from(buf.as_mut_slice()))?;
+
+        let mut messages = Vec::new();
+        let mut offset = 0;
+        // Like `CMSG_NXTHDR` in Linux, the trailing bytes that cannot hold a header are ignored.
+        while len - offset >= HEADER_LEN {
+            let header = CControlMessageHeader::from_bytes(&buf[offset..offset + HEADER_LEN]);
+            if header.cmsg_len < HEADER_LEN || header.cmsg_len > len - offset {
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "the length of the control message is invalid"
+                );
+            }
+            let payload = &buf[offset + HEADER_LEN..offset + header.cmsg_len];
+
+            match CSocketOptionLevel::try_from(header.cmsg_level) {
+                Ok(CSocketOptionLevel::SOL_SOCKET) => messages.push(Self::Unix(
+                    UnixControlMessage::read_from(header.cmsg_type, payload, ctx)?,
+                )),
+                _ => warn!(
+                    "the control message level {} is not supported",
+                    header.cmsg_level
+                ),
+            }
+
+            offset = (offset + header.cmsg_len).align_up(CMSG_ALIGN).min(len);
+        }
+
+        Ok(messages)
+    }
+
+    /// Writes the control messages to the user space.
+    ///
+    /// The control messages (or part of them) that do not fit into the user buffer are
+    /// discarded. This method returns the number of bytes written and whether any truncation
+    /// occurs, which should be reported to the user space with `MSG_CTRUNC`.
+    pub fn write_all_to_user(
+        messages: Vec<Self>,
+        addr: Vaddr,
+        len: usize,
+        is_cloexec: bool,
+        ctx: &Context,
+    ) -> Result<(usize, bool)> {
+        let mut buf = Vec::new();
+        let mut is_truncated = false;
+
+        for message in messages {
+            let max_payload_len = len.saturating_sub(buf.len() + HEADER_LEN);
+            let (level, type_, payload, is_payload_truncated) = match message {
+                Self::Unix(message) => {
+                    let (type_, payload, is_payload_truncated) =
+                        message.write_to(max_payload_len, is_cloexec, ctx);
+                    (
+                        CSocketOptionLevel::SOL_SOCKET,
+                        type_,
+                        payload,
+                        is_payload_truncated,
+                    )
+                }
+                Self::Ip(message) => message.write_to(max_payload_len),
+            };
+
+            is_truncated |= is_payload_truncated;
+            if payload.is_empty() && is_payload_truncated {
+                continue;
+            }
+
+            let header = CControlMessageHeader {
+                cmsg_len: HEADER_LEN + payload.len(),
+                cmsg_level: level as i32,
+                cmsg_type: type_,
+            };
+            buf.extend_from_slice(header.as_bytes());
+            buf.extend_from_slice(&payload);
+            buf.resize(buf.len().align_up(CMSG_ALIGN).min(len), 0);
+        }
+
+        ctx.user_space()
+            .write_bytes(addr, &mut VmReader::from(buf.as_slice()))?;
+
+        Ok((buf.len(), is_truncated))
+    }
+}
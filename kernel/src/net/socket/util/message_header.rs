@@ -1,32 +1,47 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use super::socket_addr::SocketAddr;
+use super::{
+    control_message::ControlMessage, send_recv_flags::SendRecvFlags, socket_addr::SocketAddr,
+};
 use crate::prelude::*;
 
 /// Message header used for sendmsg/recvmsg.
 #[derive(Debug)]
 pub struct MessageHeader {
     pub(in crate::net) addr: Option<SocketAddr>,
-    pub(in crate::net) control_message: Option<ControlMessage>,
+    pub(in crate::net) control_messages: Vec<ControlMessage>,
+    /// The flags of the received message, which are reported in `msg_flags`.
+    pub(in crate::net) flags: SendRecvFlags,
 }
 
 impl MessageHeader {
     /// Creates a new `MessageHeader`.
-    pub const fn new(addr: Option<SocketAddr>, control_message: Option<ControlMessage>) -> Self {
+    pub const fn new(addr: Option<SocketAddr>, control_messages: Vec<ControlMessage>) -> Self {
         Self {
             addr,
-            control_message,
+            control_messages,
+            flags: SendRecvFlags::empty(),
         }
     }
 
+    /// Sets the flags of the received message.
+    pub(in crate::net) fn with_flags(mut self, flags: SendRecvFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
     /// Returns the socket address.
     pub fn addr(&self) -> Option<&SocketAddr> {
         self.addr.as_ref()
     }
-}
 
-/// Control message carried by MessageHeader.
-///
-/// TODO: Implement the struct. The struct is empty now.
-#[derive(Debug)]
-pub struct ControlMessage;
+    /// Returns the flags of the received message.
+    pub fn flags(&self) -> SendRecvFlags {
+        self.flags
+    }
+
+    /// Takes the control messages out of the header.
+    pub fn take_control_messages(&mut self) -> Vec<ControlMessage> {
+        core::mem::take(&mut self.control_messages)
+    }
+}
@@ -1,5 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
+mod control_message;
 pub mod datagram_common;
 mod message_header;
 pub mod options;
@@ -7,4 +8,28 @@ pub mod send_recv_flags;
 pub mod shutdown_cmd;
 pub mod socket_addr;
 
+pub use control_message::ControlMessage;
 pub use message_header::MessageHeader;
+use send_recv_flags::SendRecvFlags;
+
+use crate::{
+    prelude::*,
+    process::{
+        posix_thread::AsPosixThread,
+        signal::{constants::SIGPIPE, signals::kernel::KernelSignal},
+    },
+};
+
+/// Sends `SIGPIPE` to the current thread if sending to a connection-oriented socket fails with
+/// `EPIPE`, unless `MSG_NOSIGNAL` is specified.
+pub fn send_sigpipe_on_epipe<T>(res: &Result<T>, flags: SendRecvFlags) {
+    if flags.contains(SendRecvFlags::MSG_NOSIGNAL)
+        || !res.as_ref().is_err_and(|err| err.error() == Errno::EPIPE)
+    {
+        return;
+    }
+
+    let current_thread = current_thread!();
+    let posix_thread = current_thread.as_posix_thread().unwrap();
+    posix_thread.enqueue_signal(Box::new(KernelSignal::new(SIGPIPE)));
+}
@@ -9,7 +9,8 @@ use aster_bigtcp::socket::{
 use crate::{
     match_sock_option_mut, match_sock_option_ref,
     net::socket::options::{
-        KeepAlive, Linger, RecvBuf, ReuseAddr, ReusePort, SendBuf, SocketOption,
+        BusyPoll, KeepAlive, Linger, OobInline, RecvBuf, ReuseAddr, ReusePort, SendBuf,
+        SocketOption, Timestamp, TimestampNs,
     },
     prelude::*,
 };
@@ -24,6 +25,11 @@ pub struct SocketOptionSet {
     recv_buf: u32,
     linger: LingerOption,
     keep_alive: bool,
+    /// Whether the urgent data is left in the normal data stream, which is set by `SO_OOBINLINE`.
+    oob_inline: bool,
+    timestamp: Option<TimestampFormat>,
+    /// The time to busy poll before blocking in microseconds, which is set by `SO_BUSY_POLL`.
+    busy_poll: u32,
 }
 
 impl SocketOptionSet {
@@ -36,6 +42,9 @@ impl SocketOptionSet {
             recv_buf: TCP_RECV_BUF_LEN as u32,
             linger: LingerOption::default(),
             keep_alive: false,
+            oob_inline: false,
+            timestamp: None,
+            busy_poll: 0,
         }
     }
 
@@ -48,6 +57,9 @@ impl SocketOptionSet {
             recv_buf: UDP_RECV_PAYLOAD_LEN as u32,
             linger: LingerOption::default(),
             keep_alive: false,
+            oob_inline: false,
+            timestamp: None,
+            busy_poll: 0,
         }
     }
 
@@ -82,6 +94,22 @@ impl SocketOptionSet {
                 let keep_alive = self.keep_alive();
                 socket_keepalive.set(keep_alive);
             },
+            socket_oob_inline: OobInline => {
+                let oob_inline = self.oob_inline();
+                socket_oob_inline.set(oob_inline);
+            },
+            socket_timestamp: Timestamp => {
+                let timestamp = self.timestamp() == Some(TimestampFormat::Timeval);
+                socket_timestamp.set(timestamp);
+            },
+            socket_timestamp_ns: TimestampNs => {
+                let timestamp_ns = self.timestamp() == Some(TimestampFormat::Timespec);
+                socket_timestamp_ns.set(timestamp_ns);
+            },
+            socket_busy_poll: BusyPoll => {
+                let busy_poll = self.busy_poll();
+                socket_busy_poll.set(busy_poll);
+            },
             _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to get is unknown")
         });
         Ok(())
@@ -113,6 +141,7 @@ impl SocketOptionSet {
             socket_reuse_addr: ReuseAddr => {
                 let reuse_addr = socket_reuse_addr.get().unwrap();
                 self.set_reuse_addr(*reuse_addr);
+                socket.set_reuse_addr(*reuse_addr);
             },
             socket_reuse_port: ReusePort => {
                 let reuse_port = socket_reuse_port.get().unwrap();
@@ -127,6 +156,26 @@ impl SocketOptionSet {
                 self.set_keep_alive(*keep_alive);
                 return Ok(socket.set_keep_alive(*keep_alive));
             },
+            socket_oob_inline: OobInline => {
+                let oob_inline = socket_oob_inline.get().unwrap();
+                self.set_oob_inline(*oob_inline);
+                socket.set_oob_inline(*oob_inline);
+            },
+            socket_timestamp: Timestamp => {
+                let timestamp = socket_timestamp.get().unwrap();
+                self.set_timestamp(timestamp.then_some(TimestampFormat::Timeval));
+            },
+            socket_timestamp_ns: TimestampNs => {
+                let timestamp_ns = socket_timestamp_ns.get().unwrap();
+                self.set_timestamp(timestamp_ns.then_some(TimestampFormat::Timespec));
+            },
+            socket_busy_poll: BusyPoll => {
+                let busy_poll = socket_busy_poll.get().unwrap();
+                if (*busy_poll as i32) < 0 {
+                    return_errno_with_message!(Errno::EINVAL, "the busy poll time is negative");
+                }
+                self.set_busy_poll(*busy_poll);
+            },
             _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to be set is unknown")
         });
 
@@ -157,10 +206,28 @@ impl LingerOption {
     }
 }
 
+/// The format of the receive timestamps, which are enabled by `SO_TIMESTAMP` or `SO_TIMESTAMPNS`.
+///
+/// Like Linux, enabling one of the options overrides the other one, and disabling either of them
+/// disables the receive timestamps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// The timestamps are in `struct timeval`.
+    Timeval,
+    /// The timestamps are in `struct timespec`.
+    Timespec,
+}
+
 /// A trait used for setting socket level options on actual sockets.
 pub(in crate::net) trait SetSocketLevelOption {
+    /// Sets whether the bound address can be reused by other sockets.
+    fn set_reuse_addr(&self, _reuse_addr: bool) {}
+
     /// Sets whether keepalive messages are enabled.
     fn set_keep_alive(&self, _keep_alive: bool) -> NeedIfacePoll {
         NeedIfacePoll::FALSE
     }
+
+    /// Sets whether the urgent data is left in the normal data stream.
+    fn set_oob_inline(&self, _oob_inline: bool) {}
 }
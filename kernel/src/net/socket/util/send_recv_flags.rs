@@ -32,12 +32,15 @@ bitflags! {
         // const MSG_EOF         MSG_FIN
         const MSG_NO_SHARED_FRAGS = 0x80000; /* sendpage() internal : page frags are not shared */
         const MSG_SENDPAGE_DECRYPTED	= 0x100000; /* sendpage() internal : page may carry plain text and require encryption */
+        const MSG_FASTOPEN	= 0x20000000; /* Send data in TCP SYN */
+        const MSG_CMSG_CLOEXEC	= 0x40000000; /* Set close_on_exec for file descriptors received through SCM_RIGHTS */
     }
 }
 
 impl SendRecvFlags {
     fn supported_flags() -> Self {
-        SendRecvFlags::empty()
+        // `MSG_CMSG_CLOEXEC` is handled when the control messages are written to the user space.
+        SendRecvFlags::MSG_NOSIGNAL | SendRecvFlags::MSG_CMSG_CLOEXEC
     }
 
     pub fn is_all_supported(&self) -> bool {
@@ -19,6 +19,7 @@ use crate::{
 pub struct VsockStreamSocket {
     status: RwLock<Status>,
     is_nonblocking: AtomicBool,
+    is_async: AtomicBool,
 }
 
 pub enum Status {
@@ -33,6 +34,7 @@ impl VsockStreamSocket {
         Self {
             status: RwLock::new(Status::Init(init)),
             is_nonblocking: AtomicBool::new(nonblocking),
+            is_async: AtomicBool::new(false),
         }
     }
 
@@ -40,6 +42,7 @@ impl VsockStreamSocket {
         Self {
             status: RwLock::new(Status::Connected(connected)),
             is_nonblocking: AtomicBool::new(false),
+            is_async: AtomicBool::new(false),
         }
     }
 
@@ -124,6 +127,14 @@ impl SocketPrivate for VsockStreamSocket {
     fn set_nonblocking(&self, nonblocking: bool) {
         self.is_nonblocking.store(nonblocking, Ordering::Relaxed);
     }
+
+    fn is_async(&self) -> bool {
+        self.is_async.load(Ordering::Relaxed)
+    }
+
+    fn set_async(&self, is_async: bool) {
+        self.is_async.store(is_async, Ordering::Relaxed);
+    }
 }
 
 impl Socket for VsockStreamSocket {
@@ -222,7 +233,7 @@ impl Socket for VsockStreamSocket {
     }
 
     fn accept(&self) -> Result<(Arc<dyn FileLike>, SocketAddr)> {
-        self.block_on(IoEvents::IN, || self.try_accept())
+        self.block_on_exclusive(IoEvents::IN, || self.try_accept())
     }
 
     fn shutdown(&self, cmd: SockShutdownCmd) -> Result<()> {
@@ -246,10 +257,10 @@ impl Socket for VsockStreamSocket {
         }
 
         let MessageHeader {
-            control_message, ..
+            control_messages, ..
         } = message_header;
 
-        if control_message.is_some() {
+        if !control_messages.is_empty() {
             // TODO: Support sending control message
             warn!("sending control message is not supported");
         }
@@ -271,7 +282,7 @@ impl Socket for VsockStreamSocket {
 
         // TODO: Receive control message
 
-        let messsge_header = MessageHeader::new(None, None);
+        let messsge_header = MessageHeader::new(None, Vec::new());
 
         Ok((received_bytes, messsge_header))
     }
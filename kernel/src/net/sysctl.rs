@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Tunable network parameters.
+//!
+//! The parameters are exposed to the user space via the files in `/proc/sys/net`.
+
+use core::sync::atomic::{AtomicI32, Ordering};
+
+use aster_bigtcp::iface::IP_LOCAL_PORT_START;
+
+use crate::prelude::*;
+
+/// The maximum length of the accept queue of a listening socket (i.e., `net.core.somaxconn`).
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/linux/socket.h#L297>
+static SOMAXCONN: AtomicI32 = AtomicI32::new(4096);
+
+/// Returns the maximum length of the accept queue of a listening socket.
+pub fn somaxconn() -> usize {
+    SOMAXCONN.load(Ordering::Relaxed) as usize
+}
+
+/// Sets the maximum length of the accept queue of a listening socket.
+pub fn set_somaxconn(somaxconn: i32) -> Result<()> {
+    if somaxconn < 0 {
+        return_errno_with_message!(Errno::EINVAL, "the maximum length cannot be negative");
+    }
+
+    SOMAXCONN.store(somaxconn, Ordering::Relaxed);
+    Ok(())
+}
+
+bitflags! {
+    /// The TCP Fast Open modes (i.e., `net.ipv4.tcp_fastopen`).
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/net/tcp.h#L1864>
+    pub struct TcpFastOpenMode: i32 {
+        /// Allows sending data in the opening SYN on the client.
+        const CLIENT_ENABLE = 0x1;
+        /// Allows accepting data in the opening SYN on the server.
+        const SERVER_ENABLE = 0x2;
+    }
+}
+
+static TCP_FASTOPEN: AtomicI32 = AtomicI32::new(TcpFastOpenMode::CLIENT_ENABLE.bits());
+
+/// Returns the raw value of the TCP Fast Open modes.
+pub fn tcp_fastopen() -> i32 {
+    TCP_FASTOPEN.load(Ordering::Relaxed)
+}
+
+/// Returns the TCP Fast Open modes, ignoring the unknown bits.
+pub fn tcp_fastopen_mode() -> TcpFastOpenMode {
+    TcpFastOpenMode::from_bits_truncate(tcp_fastopen())
+}
+
+/// Sets the raw value of the TCP Fast Open modes.
+///
+/// Like Linux, the unknown bits are kept, so they can be read back.
+pub fn set_tcp_fastopen(tcp_fastopen: i32) {
+    TCP_FASTOPEN.store(tcp_fastopen, Ordering::Relaxed);
+}
+
+/// The maximum number of TCP connections in the TIME-WAIT state (i.e.,
+/// `net.ipv4.tcp_max_tw_buckets`).
+///
+/// If the limit has been reached, the TCP connections are closed instead of entering the
+/// TIME-WAIT state. Linux chooses the default value according to the size of its connection hash
+/// table, and this is a typical value on small systems.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/ipv4/tcp_ipv4.c>
+static TCP_MAX_TW_BUCKETS: AtomicI32 = AtomicI32::new(32768);
+
+/// Returns the maximum number of TCP connections in the TIME-WAIT state.
+pub fn tcp_max_tw_buckets() -> usize {
+    TCP_MAX_TW_BUCKETS.load(Ordering::Relaxed) as usize
+}
+
+/// Sets the maximum number of TCP connections in the TIME-WAIT state.
+pub fn set_tcp_max_tw_buckets(tcp_max_tw_buckets: i32) -> Result<()> {
+    if tcp_max_tw_buckets < 0 {
+        return_errno_with_message!(Errno::EINVAL, "the maximum number cannot be negative");
+    }
+
+    TCP_MAX_TW_BUCKETS.store(tcp_max_tw_buckets, Ordering::Relaxed);
+    Ok(())
+}
+
+/// The first port that can be bound without `CAP_NET_BIND_SERVICE` (i.e.,
+/// `net.ipv4.ip_unprivileged_port_start`).
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/net/ip.h>
+static IP_UNPRIVILEGED_PORT_START: AtomicI32 = AtomicI32::new(1024);
+
+/// Returns the first port that can be bound without `CAP_NET_BIND_SERVICE`.
+pub fn ip_unprivileged_port_start() -> u16 {
+    IP_UNPRIVILEGED_PORT_START.load(Ordering::Relaxed) as u16
+}
+
+/// Sets the first port that can be bound without `CAP_NET_BIND_SERVICE`.
+///
+/// Like Linux, the privileged ports cannot overlap with the ephemeral ports.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/ipv4/sysctl_net_ipv4.c>
+pub fn set_ip_unprivileged_port_start(port: i32) -> Result<()> {
+    if !(0..=IP_LOCAL_PORT_START as i32).contains(&port) {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the privileged ports cannot overlap with the ephemeral ports"
+        );
+    }
+
+    IP_UNPRIVILEGED_PORT_START.store(port, Ordering::Relaxed);
+    Ok(())
+}
@@ -0,0 +1,219 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Orderly shutdown of the system.
+//!
+//! Before the machine is powered off or restarted, the system is torn down in the following
+//! order:
+//! 1. The user processes are notified with `SIGTERM`, and those that do not exit in time are
+//!    killed with `SIGKILL`;
+//! 2. The file systems are synced, so that no dirty data are lost;
+//! 3. The virtio devices are reset, so that they stop accessing the memory and raising
+//!    interrupts.
+//!
+//! The shutdown is triggered either by the `reboot` system call or by the system events, i.e.,
+//! pressing the power button or the Ctrl-Alt-Del keystroke.
+//!
+//! The system events are routed to the init process first, so that the init system can shut down
+//! the system in its own way (e.g., by stopping the services):
+//! - The power button is reported to the init process with `SIGPWR` if the init process handles
+//!   the signal. Otherwise, the kernel shuts down the system by itself.
+//! - The Ctrl-Alt-Del keystroke restarts the system immediately if it is enabled with the
+//!   `reboot` system call, which is the default. Otherwise, it is reported to the init process
+//!   with `SIGINT`, as in Linux.
+
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use aster_input::{
+    key::{Key, KeyStatus},
+    InputEvent,
+};
+use ostd::{
+    power::ExitCode,
+    sync::{WaitQueue, Waiter},
+};
+
+use crate::{
+    prelude::*,
+    process::{
+        self, process_table,
+        signal::{
+            constants::{SIGINT, SIGKILL, SIGPWR, SIGTERM},
+            sig_action::SigAction,
+            sig_num::SigNum,
+            signals::kernel::KernelSignal,
+            Pause,
+        },
+        Process,
+    },
+    thread::work_queue::{submit_work_func, WorkPriority},
+};
+
+/// The action to take after the system is torn down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerAction {
+    /// Powers off the machine.
+    PowerOff,
+    /// Restarts the machine.
+    Restart,
+}
+
+/// The time that the processes are given to exit after receiving `SIGTERM`.
+const TERMINATION_GRACE_PERIOD: Duration = Duration::from_secs(3);
+/// The time that the processes are given to exit after receiving `SIGKILL`.
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(1);
+/// The interval to check whether the processes have exited.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub(super) fn init() {
+    ostd::power::register_power_button_handler(handle_power_button);
+
+    for (_, device) in aster_input::all_devices() {
+        device.register_callbacks(&handle_input_event);
+    }
+}
+
+fn handle_power_button() {
+    // The signal dispositions are protected by a mutex and the shutdown may block, so they cannot
+    // be handled in the interrupt context.
+    submit_work_func(report_power_button, WorkPriority::High);
+}
+
+fn report_power_button() {
+    let Some(init_process) = process::get_init_process() else {
+        shutdown(PowerAction::PowerOff);
+    };
+
+    // Checking the disposition and sending the signal are not atomic. If the init process
+    // changes the disposition in between, the power button press may be missed, which is
+    // harmless because it can be pressed again.
+    let is_handled = matches!(
+        init_process.sig_dispositions().lock().get(SIGPWR),
+        SigAction::User { .. }
+    );
+    if is_handled {
+        init_process.enqueue_signal(KernelSignal::new(SIGPWR));
+    } else {
+        shutdown(PowerAction::PowerOff);
+    }
+}
+
+static IS_CAD_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Enables or disables restarting the system with the Ctrl-Alt-Del keystroke.
+///
+/// If it is disabled, the keystroke is reported to the init process with `SIGINT`.
+pub fn set_ctrl_alt_del_enabled(is_enabled: bool) {
+    IS_CAD_ENABLED.store(is_enabled, Ordering::Relaxed);
+}
+
+fn handle_ctrl_alt_del() {
+    submit_work_func(report_ctrl_alt_del, WorkPriority::High);
+}
+
+fn report_ctrl_alt_del() {
+    if IS_CAD_ENABLED.load(Ordering::Relaxed) {
+        shutdown(PowerAction::Restart);
+    } else if let Some(init_process) = process::get_init_process() {
+        init_process.enqueue_signal(KernelSignal::new(SIGINT));
+    }
+}
+
+fn handle_input_event(event: InputEvent) {
+    static IS_CTRL_PRESSED: AtomicBool = AtomicBool::new(false);
+    static IS_ALT_PRESSED: AtomicBool = AtomicBool::new(false);
+
+    let InputEvent::KeyBoard(key, status) = event;
+    let is_pressed = status == KeyStatus::Pressed;
+    match key {
+        Key::LeftCtrl | Key::RightCtrl => IS_CTRL_PRESSED.store(is_pressed, Ordering::Relaxed),
+        Key::LeftAlt | Key::RightAlt => IS_ALT_PRESSED.store(is_pressed, Ordering::Relaxed),
+        Key::Delete
+            if is_pressed
+                && IS_CTRL_PRESSED.load(Ordering::Relaxed)
+                && IS_ALT_PRESSED.load(Ordering::Relaxed) =>
+        {
+            handle_ctrl_alt_del()
+        }
+        Key::Power if is_pressed => handle_power_button(),
+        _ => (),
+    }
+}
+
+/// Tears down the system in an orderly way and then performs the power action.
+///
+/// Only the first call takes effect. If the system is already shutting down, this function
+/// blocks forever.
+pub fn shutdown(action: PowerAction) -> ! {
+    static IS_SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+    if IS_SHUTTING_DOWN.swap(true, Ordering::Relaxed) {
+        WaitQueue::new().wait_until(|| None::<()>);
+        unreachable!("no one can wake up the wait queue");
+    }
+
+    println!("[kernel] Shutting down the system: {:?}", action);
+
+    terminate_processes();
+
+    #[cfg(target_arch = "x86_64")]
+    crate::profiler::dump();
+
+    if let Err(err) = crate::fs::rootfs::root_mount().sync() {
+        warn!("failed to sync the file systems: {:?}", err);
+    }
+
+    aster_virtio::reset_all_devices();
+
+    match action {
+        PowerAction::PowerOff => ostd::power::poweroff(ExitCode::Success),
+        PowerAction::Restart => ostd::power::restart(),
+    }
+}
+
+/// Terminates all the user processes except the init process and the current process.
+///
+/// The init process is responsible for reaping the terminated processes, so it is kept alive.
+fn terminate_processes() {
+    if !signal_processes(SIGTERM, TERMINATION_GRACE_PERIOD) {
+        signal_processes(SIGKILL, KILL_GRACE_PERIOD);
+    }
+}
+
+/// Sends the signal to the processes and waits for them to exit.
+///
+/// Returns whether all the processes have exited before the timeout.
+fn signal_processes(signum: SigNum, timeout: Duration) -> bool {
+    let current = Process::current();
+    let processes: Vec<Arc<Process>> = process_table::process_table_mut()
+        .iter()
+        .filter(|process| {
+            !process.is_init_process()
+                && !process.status().is_zombie()
+                && current
+                    .as_ref()
+                    .is_none_or(|current| !Arc::ptr_eq(current, *process))
+        })
+        .cloned()
+        .collect();
+
+    for process in processes.iter() {
+        process.enqueue_signal(KernelSignal::new(signum));
+    }
+
+    let has_exited = || processes.iter().all(|process| process.status().is_zombie());
+
+    // The waiter is never woken up, so it is only used to sleep for the poll interval.
+    let waiter = Waiter::new_pair().0;
+    let nr_polls = timeout.as_millis() / EXIT_POLL_INTERVAL.as_millis();
+    for _ in 0..nr_polls {
+        if has_exited() {
+            return true;
+        }
+        let _ = waiter.pause_timeout(&(&EXIT_POLL_INTERVAL).into());
+    }
+
+    has_exited()
+}
@@ -47,7 +47,9 @@ macro_rules! current_thread {
     };
 }
 
-pub(crate) use aster_logger::{print, println};
+pub(crate) use aster_logger::{
+    debug_ratelimited, error_ratelimited, info_ratelimited, print, println, warn_ratelimited,
+};
 
 pub(crate) use crate::{
     context::{Context, CurrentUserSpace, ReadCString},
@@ -0,0 +1,407 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Control groups (cgroups).
+//!
+//! The cgroups form a single hierarchy, as in cgroup v2. Every process belongs to exactly one
+//! cgroup, which is inherited by its children. The resources used by the processes in a cgroup
+//! are limited by the following controllers:
+//!  - The CPU controller limits the CPU time of the threads in the FAIR scheduling class (see
+//!    [`CpuBandwidth`]);
+//!  - The memory controller limits the anonymous memory pages that are charged to a cgroup
+//!    when they are allocated (see [`MemoryCharge`]).
+//!
+//! Besides, a cgroup can be frozen with all its processes, e.g., to pause a container. Unlike
+//! `SIGSTOP`, freezing is invisible to the processes and their parents (see [`freeze_current`]).
+//!
+//! The limits of a cgroup also apply to all of its descendants. The cgroups are exposed to the
+//! user space by the cgroup file system (see [`crate::fs::cgroupfs`]).
+
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+
+use ostd::sync::WaitQueue;
+use spin::Once;
+
+use super::{posix_thread::AsPosixThread, process_table, Process};
+use crate::{prelude::*, sched::CpuBandwidth, thread::AsThread};
+
+/// A control group.
+pub struct Cgroup {
+    name: String,
+    parent: Option<Arc<Cgroup>>,
+    children: Mutex<BTreeMap<String, Arc<Cgroup>>>,
+    /// Whether the cgroup has been removed from the hierarchy.
+    is_removed: AtomicBool,
+    /// The controllers enabled for the children.
+    subtree_control: AtomicU32,
+    cpu_bandwidth: Arc<CpuBandwidth>,
+    /// The memory usage, in bytes.
+    memory_current: AtomicUsize,
+    /// The memory limit, in bytes.
+    memory_max: AtomicUsize,
+    /// Whether the cgroup is frozen by `cgroup.freeze`.
+    freeze: AtomicBool,
+    /// Whether the cgroup or any of its ancestors is frozen by `cgroup.freeze`.
+    is_freezing: AtomicBool,
+}
+
+bitflags! {
+    /// The controllers of cgroups.
+    pub struct Controllers: u32 {
+        const CPU = 1 << 0;
+        const MEMORY = 1 << 1;
+    }
+}
+
+impl Controllers {
+    /// Returns the controller with the given name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "cpu" => Some(Self::CPU),
+            "memory" => Some(Self::MEMORY),
+            _ => None,
+        }
+    }
+
+    /// Returns the names of the controllers separated by spaces.
+    pub fn names(&self) -> String {
+        let mut names = Vec::new();
+        if self.contains(Self::CPU) {
+            names.push("cpu");
+        }
+        if self.contains(Self::MEMORY) {
+            names.push("memory");
+        }
+        names.join(" ")
+    }
+}
+
+static ROOT_CGROUP: Once<Arc<Cgroup>> = Once::new();
+
+/// The lock that serializes the updates of `cgroup.freeze`.
+static FREEZER_LOCK: Mutex<()> = Mutex::new(());
+/// The wait queue of the frozen threads, which are woken up when the cgroups are thawed.
+static FREEZER_WAIT_QUEUE: WaitQueue = WaitQueue::new();
+
+impl Cgroup {
+    /// Returns the root cgroup.
+    pub fn root() -> &'static Arc<Cgroup> {
+        ROOT_CGROUP.call_once(|| Self::new(String::new(), None))
+    }
+
+    fn new(name: String, parent: Option<Arc<Cgroup>>) -> Arc<Self> {
+        let cpu_bandwidth =
+            CpuBandwidth::new(parent.as_ref().map(|parent| parent.cpu_bandwidth.clone()));
+        // The lock of the children of the parent is held, so the parent cannot be frozen or
+        // thawed concurrently.
+        let is_freezing = parent.as_ref().is_some_and(|parent| parent.is_freezing());
+
+        Arc::new(Self {
+            name,
+            parent,
+            children: Mutex::new(BTreeMap::new()),
+            is_removed: AtomicBool::new(false),
+            subtree_control: AtomicU32::new(0),
+            cpu_bandwidth,
+            memory_current: AtomicUsize::new(0),
+            memory_max: AtomicUsize::new(usize::MAX),
+            freeze: AtomicBool::new(false),
+            is_freezing: AtomicBool::new(is_freezing),
+        })
+    }
+
+    /// Returns the name of the cgroup, which is empty for the root cgroup.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn parent(&self) -> Option<&Arc<Cgroup>> {
+        self.parent.as_ref()
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.parent.is_none()
+    }
+
+    /// Returns the path of the cgroup relative to the root cgroup (e.g., `/a/b`).
+    pub fn path(&self) -> String {
+        let mut names: Vec<&str> =
+            core::iter::successors(Some(self), |cgroup| cgroup.parent().map(Arc::as_ref))
+                .map(|cgroup| cgroup.name())
+                .collect();
+        names.reverse();
+
+        if names.len() == 1 {
+            String::from("/")
+        } else {
+            names.join("/")
+        }
+    }
+
+    // *********** Hierarchy ***********
+
+    /// Creates a child cgroup.
+    pub fn create_child(self: &Arc<Self>, name: &str) -> Result<Arc<Cgroup>> {
+        let mut children = self.children.lock();
+        if self.is_removed.load(Ordering::Relaxed) {
+            return_errno_with_message!(Errno::ENOENT, "the cgroup has been removed");
+        }
+        if children.contains_key(name) {
+            return_errno_with_message!(Errno::EEXIST, "the cgroup already exists");
+        }
+
+        let child = Self::new(name.to_string(), Some(self.clone()));
+        children.insert(name.to_string(), child.clone());
+        Ok(child)
+    }
+
+    /// Removes a child cgroup.
+    ///
+    /// The child cgroup cannot be removed if it has children or processes.
+    pub fn remove_child(&self, name: &str) -> Result<()> {
+        let mut children = self.children.lock();
+        let Some(child) = children.get(name) else {
+            return_errno_with_message!(Errno::ENOENT, "the cgroup does not exist");
+        };
+
+        // Holding the lock of the children prevents new children from being created.
+        let child_children = child.children.lock();
+        if !child_children.is_empty() {
+            return_errno_with_message!(Errno::EBUSY, "the cgroup has children");
+        }
+        if !child.processes().is_empty() {
+            return_errno_with_message!(Errno::EBUSY, "the cgroup has processes");
+        }
+        child.is_removed.store(true, Ordering::Relaxed);
+        drop(child_children);
+
+        children.remove(name);
+        Ok(())
+    }
+
+    pub fn child(&self, name: &str) -> Option<Arc<Cgroup>> {
+        self.children.lock().get(name).cloned()
+    }
+
+    pub fn children(&self) -> Vec<Arc<Cgroup>> {
+        self.children.lock().values().cloned().collect()
+    }
+
+    pub fn subtree_control(&self) -> Controllers {
+        Controllers::from_bits_truncate(self.subtree_control.load(Ordering::Relaxed))
+    }
+
+    /// Enables and disables the controllers for the children.
+    //
+    // TODO: Enforce the limits only if the controllers are enabled in the parent cgroup. For
+    // now, all the controllers are always effective.
+    pub fn update_subtree_control(&self, enabled: Controllers, disabled: Controllers) {
+        let subtree_control = (self.subtree_control() | enabled) - disabled;
+        self.subtree_control
+            .store(subtree_control.bits(), Ordering::Relaxed);
+    }
+
+    // *********** Processes ***********
+
+    /// Returns the processes in the cgroup.
+    ///
+    /// Zombie processes are not counted, since they have released their resources.
+    pub fn processes(self: &Arc<Self>) -> Vec<Arc<Process>> {
+        process_table::process_table_mut()
+            .iter()
+            .filter(|process| !process.status().is_zombie() && Arc::ptr_eq(&process.cgroup(), self))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns whether the cgroup or any of its descendants has processes.
+    pub fn is_populated(self: &Arc<Self>) -> bool {
+        !self.processes().is_empty() || self.children().iter().any(Cgroup::is_populated)
+    }
+
+    /// Moves the process with all its threads into the cgroup.
+    pub fn attach(self: &Arc<Self>, process: &Process) -> Result<()> {
+        // Holding the lock of the children prevents the cgroup from being removed.
+        let _children = self.children.lock();
+        if self.is_removed.load(Ordering::Relaxed) {
+            return_errno_with_message!(Errno::ENOENT, "the cgroup has been removed");
+        }
+
+        let tasks = process.tasks().lock();
+        process.set_cgroup(self.clone());
+        for task in tasks.as_slice() {
+            let thread = task.as_thread().unwrap();
+            thread
+                .sched_attr()
+                .set_cpu_bandwidth(Some(self.cpu_bandwidth.clone()));
+            // The threads are frozen if the new cgroup is frozen, or thawed otherwise.
+            if self.is_freezing() {
+                thread.as_posix_thread().unwrap().wake_up_interruptible();
+            }
+        }
+        FREEZER_WAIT_QUEUE.wake_all();
+
+        Ok(())
+    }
+
+    // *********** Freezer ***********
+
+    /// Returns whether the cgroup is frozen by `cgroup.freeze`.
+    ///
+    /// The cgroup is also frozen if any of its ancestors is frozen, which is reported by
+    /// [`Self::is_freezing`] instead.
+    pub fn freeze(&self) -> bool {
+        self.freeze.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether the threads in the cgroup should be frozen.
+    pub fn is_freezing(&self) -> bool {
+        self.is_freezing.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether all the threads in the cgroup and its descendants have been frozen.
+    pub fn is_frozen(self: &Arc<Self>) -> bool {
+        self.is_freezing()
+            && self.processes().iter().all(|process| {
+                process.tasks().lock().as_slice().iter().all(|task| {
+                    let thread = task.as_thread().unwrap();
+                    thread.is_exited() || thread.as_posix_thread().unwrap().is_frozen()
+                })
+            })
+            && self.children().iter().all(Cgroup::is_frozen)
+    }
+
+    /// Freezes or thaws the cgroup with its descendants.
+    ///
+    /// The threads are not frozen at once. They are interrupted like by a signal, and freeze
+    /// themselves before returning to the user space (see [`freeze_current`]).
+    pub fn set_freeze(&self, freeze: bool) {
+        let _guard = FREEZER_LOCK.lock();
+
+        self.freeze.store(freeze, Ordering::Relaxed);
+        let is_parent_freezing = self.parent().is_some_and(|parent| parent.is_freezing());
+        self.update_freezing(is_parent_freezing);
+
+        for process in process_table::process_table_mut().iter() {
+            if !process.cgroup().is_freezing() {
+                continue;
+            }
+            for task in process.tasks().lock().as_slice() {
+                task.as_posix_thread().unwrap().wake_up_interruptible();
+            }
+        }
+        FREEZER_WAIT_QUEUE.wake_all();
+    }
+
+    fn update_freezing(&self, is_parent_freezing: bool) {
+        // Holding the lock of the children prevents new children from being created with the
+        // outdated state.
+        let children = self.children.lock();
+        let is_freezing = is_parent_freezing || self.freeze();
+        self.is_freezing.store(is_freezing, Ordering::Relaxed);
+        for child in children.values() {
+            child.update_freezing(is_freezing);
+        }
+    }
+
+    // *********** CPU ***********
+
+    pub fn cpu_bandwidth(&self) -> &Arc<CpuBandwidth> {
+        &self.cpu_bandwidth
+    }
+
+    // *********** Memory ***********
+
+    /// Returns the memory usage, in bytes.
+    pub fn memory_current(&self) -> usize {
+        self.memory_current.load(Ordering::Relaxed)
+    }
+
+    /// Returns the memory limit, in bytes.
+    ///
+    /// A limit of `None` means that the memory usage is unlimited.
+    pub fn memory_max(&self) -> Option<usize> {
+        let max = self.memory_max.load(Ordering::Relaxed);
+        (max != usize::MAX).then_some(max)
+    }
+
+    /// Sets the memory limit, in bytes.
+    ///
+    /// If the memory usage exceeds the new limit, the memory is _not_ reclaimed, but new
+    /// allocations will fail.
+    pub fn set_memory_max(&self, max: Option<usize>) {
+        self.memory_max
+            .store(max.unwrap_or(usize::MAX), Ordering::Relaxed);
+    }
+
+    /// Charges the memory to the cgroup and its ancestors.
+    ///
+    /// This method fails with [`Errno::ENOMEM`] if the limit of any of the cgroups is exceeded.
+    pub fn try_charge(self: &Arc<Self>, nr_bytes: usize) -> Result<MemoryCharge> {
+        let mut charged = Vec::new();
+        for cgroup in core::iter::successors(Some(self), |cgroup| cgroup.parent()) {
+            let old = cgroup.memory_current.fetch_add(nr_bytes, Ordering::Relaxed);
+            charged.push(cgroup);
+
+            if old + nr_bytes > cgroup.memory_max.load(Ordering::Relaxed) {
+                for cgroup in charged {
+                    cgroup.memory_current.fetch_sub(nr_bytes, Ordering::Relaxed);
+                }
+                return_errno_with_message!(Errno::ENOMEM, "the memory limit of the cgroup is hit");
+            }
+        }
+
+        Ok(MemoryCharge {
+            cgroup: self.clone(),
+            nr_bytes,
+        })
+    }
+}
+
+/// Freezes the current thread if its cgroup is frozen, until the cgroup is thawed or the thread
+/// is killed.
+///
+/// This is called before the thread returns to the user space, where it holds no locks or
+/// resources in the kernel. The thread is neither stopped nor reported to its parent, so
+/// freezing and thawing a cgroup are invisible to the processes, unlike `SIGSTOP` and `SIGCONT`.
+pub fn freeze_current(ctx: &Context) {
+    let should_wait = || ctx.process.cgroup().is_freezing() && !ctx.posix_thread.is_killed();
+    if !should_wait() {
+        return;
+    }
+
+    ctx.posix_thread.set_frozen(true);
+    FREEZER_WAIT_QUEUE.wait_until(|| (!should_wait()).then_some(()));
+    ctx.posix_thread.set_frozen(false);
+}
+
+/// Wakes up the frozen threads, so that the killed ones can exit.
+pub(super) fn wake_up_frozen() {
+    FREEZER_WAIT_QUEUE.wake_all();
+}
+
+impl Debug for Cgroup {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Cgroup")
+            .field("path", &self.path())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The memory charged to a cgroup.
+///
+/// The memory is uncharged when the object is dropped.
+#[derive(Debug)]
+pub struct MemoryCharge {
+    cgroup: Arc<Cgroup>,
+    nr_bytes: usize,
+}
+
+impl Drop for MemoryCharge {
+    fn drop(&mut self) {
+        for cgroup in core::iter::successors(Some(&self.cgroup), |cgroup| cgroup.parent()) {
+            cgroup
+                .memory_current
+                .fetch_sub(self.nr_bytes, Ordering::Relaxed);
+        }
+    }
+}
@@ -5,26 +5,33 @@ use core::{num::NonZeroU64, sync::atomic::Ordering};
 use ostd::{cpu::context::UserContext, sync::RwArc, task::Task, user::UserContextApi};
 
 use super::{
+    cgroup::Cgroup,
+    credentials::capabilities::CapSet,
     posix_thread::{AsPosixThread, PosixThreadBuilder, ThreadName},
     process_table,
     process_vm::ProcessVm,
     rlimit::ResourceLimits,
-    signal::{constants::SIGCHLD, sig_disposition::SigDispositions, sig_num::SigNum},
+    signal::{
+        constants::SIGCHLD, sig_disposition::SigDispositions, sig_num::SigNum, Pause, SigStack,
+    },
     Credentials, Pid, Process,
 };
 use crate::{
     cpu::LinuxAbi,
     current_userspace,
     fs::{file_table::FileTable, thread_info::ThreadFsInfo},
+    net::namespace::NetNamespace,
     prelude::*,
     process::posix_thread::allocate_posix_tid,
     sched::Nice,
     thread::{AsThread, Tid},
+    time::namespace::TimeNamespace,
 };
 
 bitflags! {
     #[derive(Default)]
     pub struct CloneFlags: u32 {
+        const CLONE_NEWTIME = 0x00000080;       /* New time namespace.  */
         const CLONE_VM      = 0x00000100;       /* Set if VM shared between processes.  */
         const CLONE_FS      = 0x00000200;       /* Set if fs info shared between processes.  */
         const CLONE_FILES   = 0x00000400;       /* Set if open files shared between processes.  */
@@ -169,7 +176,10 @@ impl CloneFlags {
             | CloneFlags::CLONE_PARENT_SETTID
             | CloneFlags::CLONE_CHILD_SETTID
             | CloneFlags::CLONE_CHILD_CLEARTID
-            | CloneFlags::CLONE_VFORK;
+            | CloneFlags::CLONE_VFORK
+            | CloneFlags::CLONE_NEWTIME
+            | CloneFlags::CLONE_NEWUSER
+            | CloneFlags::CLONE_NEWNET;
         let unsupported_flags = *self - supported_flags;
         if !unsupported_flags.is_empty() {
             warn!("contains unsupported clone flags: {:?}", unsupported_flags);
@@ -206,7 +216,9 @@ pub fn clone_child(
         if child_process.status().is_vfork_child() {
             let cond = || (!child_process.status().is_vfork_child()).then_some(());
             let current = ctx.process;
-            current.children_wait_queue().wait_until(cond);
+            // Like Linux, stop waiting if the parent is killed. The parent will exit immediately
+            // after returning to the user space, so the child can run without it.
+            let _ = current.children_wait_queue().pause_until_killable(cond);
         }
 
         let child_pid = child_process.pid();
@@ -229,6 +241,21 @@ fn clone_child_task(
             "`CLONE_THREAD` without `CLONE_VM` and `CLONE_SIGHAND` is not valid"
         );
     }
+    // The threads in the same process must be in the same user namespace.
+    if clone_flags.contains(CloneFlags::CLONE_NEWUSER) {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "`CLONE_THREAD` with `CLONE_NEWUSER` is not valid"
+        );
+    }
+    // FIXME: Linux allows a thread to be in a different network namespace, but the network
+    // namespace is shared by all the threads in the same process here.
+    if clone_flags.contains(CloneFlags::CLONE_NEWNET) {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "`CLONE_THREAD` with `CLONE_NEWNET` is not supported"
+        );
+    }
 
     let Context {
         process,
@@ -244,7 +271,7 @@ fn clone_child_task(
     let child_file_table = clone_files(thread_local.borrow_file_table().unwrap(), clone_flags);
 
     // clone fs
-    let child_fs = clone_fs(posix_thread.fs(), clone_flags);
+    let child_fs = clone_fs(&posix_thread.fs().read(), clone_flags);
 
     let child_user_ctx = Arc::new(clone_user_ctx(
         parent_context,
@@ -266,6 +293,9 @@ fn clone_child_task(
 
         let mut thread_builder = PosixThreadBuilder::new(child_tid, child_user_ctx, credentials)
             .process(posix_thread.weak_process())
+            .no_new_privs(posix_thread.no_new_privs())
+            .seccomp(posix_thread.seccomp().new_inherited())
+            .io_priority(posix_thread.io_priority())
             .sig_mask(sig_mask)
             .file_table(child_file_table)
             .fs(child_fs);
@@ -301,6 +331,9 @@ fn clone_child_process(
 
     let clone_flags = clone_args.flags;
 
+    // Clone the credentials
+    let child_credentials = clone_credentials(ctx, clone_flags)?;
+
     // Clone the virtual memory space
     let child_process_vm = {
         let parent_process_vm = process.vm();
@@ -320,7 +353,7 @@ fn clone_child_process(
     let child_file_table = clone_files(thread_local.borrow_file_table().unwrap(), clone_flags);
 
     // Clone the filesystem information
-    let child_fs = clone_fs(posix_thread.fs(), clone_flags);
+    let child_fs = clone_fs(&posix_thread.fs().read(), clone_flags);
 
     // Clone signal dispositions
     let child_sig_dispositions = clone_sighand(process.sig_dispositions(), clone_flags);
@@ -331,12 +364,21 @@ fn clone_child_process(
     // Inherit the parent's signal mask
     let child_sig_mask = posix_thread.sig_mask().load(Ordering::Relaxed).into();
 
+    // Inherit the parent's alternate signal stack
+    let child_sig_stack = clone_sig_stack(thread_local.sig_stack().borrow().as_ref(), clone_flags);
+
     // Inherit the parent's resource limits
     let child_resource_limits = process.resource_limits().clone();
 
     // Inherit the parent's nice value
     let child_nice = process.nice().load(Ordering::Relaxed);
 
+    // Clone the time namespace
+    let child_time_ns = clone_time_ns(ctx, clone_flags)?;
+
+    // Clone the network namespace
+    let child_net_ns = clone_net_ns(ctx, &child_credentials, clone_flags)?;
+
     let child_tid = allocate_posix_tid();
 
     let child = {
@@ -344,14 +386,13 @@ fn clone_child_process(
         let mut child_thread_builder = {
             let child_thread_name = ThreadName::new_from_executable_path(&child_elf_path)?;
 
-            let credentials = {
-                let credentials = ctx.posix_thread.credentials();
-                Credentials::new_from(&credentials)
-            };
-
-            PosixThreadBuilder::new(child_tid, child_user_ctx, credentials)
+            PosixThreadBuilder::new(child_tid, child_user_ctx, child_credentials)
                 .thread_name(Some(child_thread_name))
+                .no_new_privs(posix_thread.no_new_privs())
+                .seccomp(posix_thread.seccomp().new_inherited())
+                .io_priority(posix_thread.io_priority())
                 .sig_mask(child_sig_mask)
+                .sig_stack(child_sig_stack)
                 .file_table(child_file_table)
                 .fs(child_fs)
         };
@@ -371,6 +412,9 @@ fn clone_child_process(
             child_resource_limits,
             child_nice,
             child_sig_dispositions,
+            process.cgroup(),
+            child_time_ns,
+            child_net_ns,
             child_thread_builder,
         )
     };
@@ -508,6 +552,79 @@ fn clone_sighand(
     }
 }
 
+fn clone_sig_stack(
+    parent_sig_stack: Option<&SigStack>,
+    clone_flags: CloneFlags,
+) -> Option<SigStack> {
+    // The child cannot use the parent's alternate signal stack if they run concurrently in the
+    // same address space.
+    if clone_flags.contains(CloneFlags::CLONE_VM) && !clone_flags.contains(CloneFlags::CLONE_VFORK)
+    {
+        None
+    } else {
+        parent_sig_stack.cloned()
+    }
+}
+
+fn clone_credentials(ctx: &Context, clone_flags: CloneFlags) -> Result<Credentials> {
+    let credentials = {
+        let credentials = ctx.posix_thread.credentials();
+        Credentials::new_from(&credentials)
+    };
+    if !clone_flags.contains(CloneFlags::CLONE_NEWUSER) {
+        return Ok(credentials);
+    }
+
+    // Like Linux, the file system information cannot be shared across user namespaces.
+    if clone_flags.contains(CloneFlags::CLONE_FS) {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "`CLONE_FS` with `CLONE_NEWUSER` is not valid"
+        );
+    }
+    credentials.enter_new_user_ns()?;
+    Ok(credentials)
+}
+
+fn clone_time_ns(ctx: &Context, clone_flags: CloneFlags) -> Result<Arc<TimeNamespace>> {
+    let time_ns = ctx.process.time_ns_for_children();
+    if !clone_flags.contains(CloneFlags::CLONE_NEWTIME) {
+        return Ok(time_ns);
+    }
+
+    if !ctx
+        .posix_thread
+        .credentials()
+        .has_capability(CapSet::SYS_ADMIN)
+    {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "creating a time namespace requires the CAP_SYS_ADMIN capability"
+        );
+    }
+    Ok(time_ns.new_child())
+}
+
+fn clone_net_ns(
+    ctx: &Context,
+    child_credentials: &Credentials,
+    clone_flags: CloneFlags,
+) -> Result<Arc<NetNamespace>> {
+    if !clone_flags.contains(CloneFlags::CLONE_NEWNET) {
+        return Ok(ctx.process.net_ns());
+    }
+
+    // The new namespace is owned by the user namespace of the child, which may be a new one.
+    let user_ns = child_credentials.user_ns();
+    if !child_credentials.has_capability_in(CapSet::SYS_ADMIN, &user_ns) {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "creating a network namespace requires the CAP_SYS_ADMIN capability"
+        );
+    }
+    Ok(NetNamespace::new(user_ns))
+}
+
 fn clone_sysvsem(clone_flags: CloneFlags) -> Result<()> {
     if clone_flags.contains(CloneFlags::CLONE_SYSVSEM) {
         warn!("CLONE_SYSVSEM is not supported now");
@@ -523,6 +640,9 @@ fn create_child_process(
     resource_limits: ResourceLimits,
     nice: Nice,
     sig_dispositions: Arc<Mutex<SigDispositions>>,
+    cgroup: Arc<Cgroup>,
+    time_ns: Arc<TimeNamespace>,
+    net_ns: Arc<NetNamespace>,
     thread_builder: PosixThreadBuilder,
 ) -> Arc<Process> {
     let child_proc = Process::new(
@@ -533,6 +653,9 @@ fn create_child_process(
         resource_limits,
         nice,
         sig_dispositions,
+        cgroup,
+        time_ns,
+        net_ns,
     );
 
     let child_task = thread_builder.process(Arc::downgrade(&child_proc)).build();
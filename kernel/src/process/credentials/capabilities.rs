@@ -56,6 +56,21 @@ bitflags! {
 impl CapSet {
     const MASK: u64 = (1 << (CapSet::most_significant_bit() + 1)) - 1;
 
+    /// The capabilities that follow the file system user ID.
+    ///
+    /// They are dropped from the effective set if the file system user ID is changed from root to
+    /// non-root, and vice versa.
+    pub const FS_MASK: CapSet = CapSet::from_bits_truncate(
+        CapSet::CHOWN.bits()
+            | CapSet::DAC_OVERRIDE.bits()
+            | CapSet::DAC_READ_SEARCH.bits()
+            | CapSet::FOWNER.bits()
+            | CapSet::FSETID.bits()
+            | CapSet::LINUX_IMMUTABLE.bits()
+            | CapSet::MKNOD.bits()
+            | CapSet::MAC_OVERRIDE.bits(),
+    );
+
     /// Converts the capability set to a `u32`. The higher bits are truncated.
     pub fn as_u32(&self) -> u32 {
         self.bits() as u32
@@ -7,7 +7,10 @@ use ostd::sync::{PreemptDisabled, RwLockReadGuard, RwLockWriteGuard};
 use super::{group::AtomicGid, user::AtomicUid, Gid, Uid};
 use crate::{
     prelude::*,
-    process::credentials::capabilities::{AtomicCapSet, CapSet},
+    process::{
+        credentials::capabilities::{AtomicCapSet, CapSet},
+        namespace::user_ns::UserNamespace,
+    },
 };
 
 #[derive(Debug)]
@@ -49,6 +52,9 @@ pub(super) struct Credentials_ {
 
     /// Keep capabilities flag
     keep_capabilities: AtomicBool,
+
+    /// The user namespace, which owns the Linux capabilities and maps the user and group ids.
+    user_ns: RwLock<Arc<UserNamespace>>,
 }
 
 impl Credentials_ {
@@ -71,11 +77,73 @@ impl Credentials_ {
             permitted_capset: AtomicCapSet::new(capset),
             effective_capset: AtomicCapSet::new(capset),
             keep_capabilities: AtomicBool::new(false),
+            user_ns: RwLock::new(UserNamespace::get_init_singleton().clone()),
         }
     }
 
-    fn is_privileged(&self) -> bool {
-        self.euid.is_root()
+    /// Returns whether the capability is in the effective capability set.
+    ///
+    /// The capabilities are only in effect for the user namespace of the credentials and its
+    /// descendants, see [`Self::has_capability_in`].
+    pub(super) fn has_capability(&self, capability: CapSet) -> bool {
+        self.effective_capset().contains(capability)
+    }
+
+    /// Returns whether the capability is in effect for the user namespace, as in Linux.
+    ///
+    /// The capability is in effect if it is in the effective capability set and the user
+    /// namespace is the one of the credentials or its descendant. Also, the owner of a user
+    /// namespace has all the capabilities in the namespace and its descendants, if the owner is
+    /// in the parent namespace.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.13/source/security/commoncap.c#L68>
+    pub(super) fn has_capability_in(&self, capability: CapSet, user_ns: &UserNamespace) -> bool {
+        let own_ns = self.user_ns();
+
+        let mut ns = user_ns;
+        loop {
+            if core::ptr::eq(ns, own_ns.as_ref()) {
+                return self.has_capability(capability);
+            }
+            // The namespace is not a descendant of the own namespace.
+            if ns.level() <= own_ns.level() {
+                return false;
+            }
+
+            let parent = ns.parent().unwrap();
+            if Arc::ptr_eq(parent, &own_ns) && ns.owner() == self.euid() {
+                return true;
+            }
+            ns = parent.as_ref();
+        }
+    }
+
+    /// Returns whether the user id is the root user in the user namespace.
+    fn is_root(&self, uid: Uid) -> bool {
+        self.user_ns()
+            .uid_to_kernel(0)
+            .is_ok_and(|root| root == uid)
+    }
+
+    //  ******* User namespace methods *******
+
+    pub(super) fn user_ns(&self) -> Arc<UserNamespace> {
+        self.user_ns.read().clone()
+    }
+
+    /// Moves the credentials to a new child user namespace, which gains all the capabilities in
+    /// the new namespace.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.13/source/kernel/user_namespace.c#L146>
+    pub(super) fn enter_new_user_ns(&self) -> Result<()> {
+        let new_ns = self.user_ns().new_child(self.euid(), self.egid())?;
+        *self.user_ns.write() = new_ns;
+
+        self.set_inheritable_capset(CapSet::empty());
+        self.set_permitted_capset(CapSet::new_root());
+        self.set_effective_capset(CapSet::new_root());
+        self.set_keep_capabilities(false);
+        Ok(())
     }
 
     //  ******* Uid methods *******
@@ -100,35 +168,34 @@ impl Credentials_ {
         self.keep_capabilities.load(Ordering::Relaxed)
     }
 
-    pub(super) fn set_uid(&self, uid: Uid) {
-        if self.is_privileged() {
+    pub(super) fn set_uid(&self, uid: Uid) -> Result<()> {
+        let (old_ruid, old_euid, old_suid) = (self.ruid(), self.euid(), self.suid());
+
+        if self.has_capability(CapSet::SETUID) {
             self.ruid.store(uid, Ordering::Relaxed);
             self.euid.store(uid, Ordering::Relaxed);
             self.suid.store(uid, Ordering::Relaxed);
             self.fsuid.store(uid, Ordering::Relaxed);
         } else {
             // Unprivileged processes can only switch between ruid, euid, suid
-            if uid != self.ruid.load(Ordering::Relaxed)
-                && uid != self.euid.load(Ordering::Relaxed)
-                && uid != self.suid.load(Ordering::Relaxed)
-            {
-                // No permission to set to this UID
-                return;
+            if uid != old_ruid && uid != old_euid && uid != old_suid {
+                return_errno_with_message!(
+                    Errno::EPERM,
+                    "uid can only be one of old ruid, old euid and old suid."
+                );
             }
             self.euid.store(uid, Ordering::Relaxed);
             self.fsuid.store(uid, Ordering::Relaxed);
         }
-        if !self.keep_capabilities.load(Ordering::Relaxed) {
-            self.set_permitted_capset(CapSet::empty());
-            self.set_inheritable_capset(CapSet::empty());
-        }
-        // Always clear the effective capabilities when changing the UID
-        self.set_effective_capset(CapSet::empty());
+
+        self.fix_capabilities_after_uid_change(old_ruid, old_euid, old_suid);
+        Ok(())
     }
 
     pub(super) fn set_reuid(&self, ruid: Option<Uid>, euid: Option<Uid>) -> Result<()> {
         self.check_uid_perm(ruid.as_ref(), euid.as_ref(), None, false)?;
 
+        let (old_ruid, old_euid, old_suid) = (self.ruid(), self.euid(), self.suid());
         let should_set_suid = ruid.is_some() || euid.is_some_and(|euid| euid != self.ruid());
 
         self.set_resuid_unchecked(ruid, euid, None);
@@ -142,6 +209,7 @@ impl Credentials_ {
         // the same as `euid`, but `setreuid` does not mention the `fsuid` should be set.
         self.fsuid.store(self.euid(), Ordering::Release);
 
+        self.fix_capabilities_after_uid_change(old_ruid, old_euid, old_suid);
         Ok(())
     }
 
@@ -153,10 +221,13 @@ impl Credentials_ {
     ) -> Result<()> {
         self.check_uid_perm(ruid.as_ref(), euid.as_ref(), suid.as_ref(), true)?;
 
+        let (old_ruid, old_euid, old_suid) = (self.ruid(), self.euid(), self.suid());
+
         self.set_resuid_unchecked(ruid, euid, suid);
 
         self.fsuid.store(self.euid(), Ordering::Release);
 
+        self.fix_capabilities_after_uid_change(old_ruid, old_euid, old_suid);
         Ok(())
     }
 
@@ -167,12 +238,12 @@ impl Credentials_ {
             return Ok(old_fsuid);
         };
 
-        if self.is_privileged() {
-            self.fsuid.store(fsuid, Ordering::Release);
-            return Ok(old_fsuid);
-        }
-
-        if fsuid != self.ruid() && fsuid != self.euid() && fsuid != self.suid() {
+        if !self.has_capability(CapSet::SETUID)
+            && fsuid != self.ruid()
+            && fsuid != self.euid()
+            && fsuid != self.suid()
+            && fsuid != old_fsuid
+        {
             return_errno_with_message!(
                 Errno::EPERM,
                 "fsuid can only be one of old ruid, old euid and old suid."
@@ -181,6 +252,15 @@ impl Credentials_ {
 
         self.fsuid.store(fsuid, Ordering::Release);
 
+        // The file system capabilities follow the file system user ID, as in Linux.
+        if self.is_root(old_fsuid) && !self.is_root(fsuid) {
+            self.set_effective_capset(self.effective_capset() - CapSet::FS_MASK);
+        } else if !self.is_root(old_fsuid) && self.is_root(fsuid) {
+            self.set_effective_capset(
+                self.effective_capset() | (self.permitted_capset() & CapSet::FS_MASK),
+            );
+        }
+
         Ok(old_fsuid)
     }
 
@@ -201,7 +281,7 @@ impl Credentials_ {
         suid: Option<&Uid>,
         ruid_may_be_old_suid: bool,
     ) -> Result<()> {
-        if self.is_privileged() {
+        if self.has_capability(CapSet::SETUID) {
             return Ok(());
         }
 
@@ -255,6 +335,59 @@ impl Credentials_ {
         }
     }
 
+    /// Adjusts the capabilities after the user IDs are changed, as in Linux.
+    ///
+    /// The capabilities are the privileges of the root user in the user namespace, so they are
+    /// lost if the process gives up the root user IDs:
+    /// - If none of the real, effective, and saved-set user IDs is root any more, the permitted
+    ///   and effective capabilities are cleared, unless the capabilities are kept by
+    ///   `PR_SET_KEEPCAPS`;
+    /// - If the effective user ID is changed from root to non-root, the effective capabilities are
+    ///   cleared;
+    /// - If the effective user ID is changed from non-root to root, the effective capabilities are
+    ///   restored from the permitted capabilities.
+    ///
+    /// Reference: <https://man7.org/linux/man-pages/man7/capabilities.7.html>.
+    fn fix_capabilities_after_uid_change(&self, old_ruid: Uid, old_euid: Uid, old_suid: Uid) {
+        let was_root = self.is_root(old_ruid) || self.is_root(old_euid) || self.is_root(old_suid);
+        let is_root =
+            self.is_root(self.ruid()) || self.is_root(self.euid()) || self.is_root(self.suid());
+        if was_root && !is_root && !self.keep_capabilities() {
+            self.set_permitted_capset(CapSet::empty());
+            self.set_effective_capset(CapSet::empty());
+        }
+
+        if self.is_root(old_euid) && !self.is_root(self.euid()) {
+            self.set_effective_capset(CapSet::empty());
+        } else if !self.is_root(old_euid) && self.is_root(self.euid()) {
+            self.set_effective_capset(self.permitted_capset());
+        }
+    }
+
+    /// Recomputes the capabilities when executing a new program.
+    ///
+    /// There are no file capabilities, so the root user in the user namespace (i.e., the real or
+    /// effective user ID is root) gains all the capabilities, and the effective capabilities are
+    /// enabled only if the effective user ID is root. The other users lose all the permitted and
+    /// effective capabilities.
+    ///
+    /// Reference: <https://man7.org/linux/man-pages/man7/capabilities.7.html>.
+    pub(super) fn update_capabilities_for_exec(&self) {
+        let permitted = if self.is_root(self.ruid()) || self.is_root(self.euid()) {
+            CapSet::new_root()
+        } else {
+            CapSet::empty()
+        };
+        let effective = if self.is_root(self.euid()) {
+            permitted
+        } else {
+            CapSet::empty()
+        };
+        self.set_permitted_capset(permitted);
+        self.set_effective_capset(effective);
+        self.set_keep_capabilities(false);
+    }
+
     //  ******* Gid methods *******
 
     pub(super) fn rgid(&self) -> Gid {
@@ -273,16 +406,24 @@ impl Credentials_ {
         self.fsgid.load(Ordering::Relaxed)
     }
 
-    pub(super) fn set_gid(&self, gid: Gid) {
-        if self.is_privileged() {
+    pub(super) fn set_gid(&self, gid: Gid) -> Result<()> {
+        if self.has_capability(CapSet::SETGID) {
             self.rgid.store(gid, Ordering::Relaxed);
             self.egid.store(gid, Ordering::Relaxed);
             self.sgid.store(gid, Ordering::Relaxed);
             self.fsgid.store(gid, Ordering::Relaxed);
         } else {
+            // Unprivileged processes can only switch between rgid, egid, sgid
+            if gid != self.rgid() && gid != self.egid() && gid != self.sgid() {
+                return_errno_with_message!(
+                    Errno::EPERM,
+                    "gid can only be one of old rgid, old egid and old sgid."
+                );
+            }
             self.egid.store(gid, Ordering::Relaxed);
             self.fsgid.store(gid, Ordering::Relaxed);
         }
+        Ok(())
     }
 
     pub(super) fn set_regid(&self, rgid: Option<Gid>, egid: Option<Gid>) -> Result<()> {
@@ -323,12 +464,12 @@ impl Credentials_ {
             return Ok(old_fsgid);
         };
 
-        if self.is_privileged() {
-            self.fsgid.store(fsgid, Ordering::Relaxed);
-            return Ok(old_fsgid);
-        }
-
-        if fsgid != self.rgid() && fsgid != self.egid() && fsgid != self.sgid() {
+        if !self.has_capability(CapSet::SETGID)
+            && fsgid != self.rgid()
+            && fsgid != self.egid()
+            && fsgid != self.sgid()
+            && fsgid != old_fsgid
+        {
             return_errno_with_message!(
                 Errno::EPERM,
                 "fsuid can only be one of old ruid, old euid and old suid."
@@ -362,7 +503,7 @@ impl Credentials_ {
         sgid: Option<&Gid>,
         rgid_may_be_old_sgid: bool,
     ) -> Result<()> {
-        if self.is_privileged() {
+        if self.has_capability(CapSet::SETGID) {
             return Ok(());
         }
 
@@ -418,6 +559,11 @@ impl Credentials_ {
 
     //  ******* Supplementary groups methods *******
 
+    /// Returns whether the group is the file system group or one of the supplementary groups.
+    pub(super) fn is_in_group(&self, gid: Gid) -> bool {
+        self.fsgid() == gid || self.groups().contains(&gid)
+    }
+
     pub(super) fn groups(&self) -> RwLockReadGuard<BTreeSet<Gid>, PreemptDisabled> {
         self.supplementary_gids.read()
     }
@@ -472,6 +618,7 @@ impl Clone for Credentials_ {
             permitted_capset: self.permitted_capset.clone(),
             effective_capset: self.effective_capset.clone(),
             keep_capabilities: AtomicBool::new(self.keep_capabilities.load(Ordering::Relaxed)),
+            user_ns: RwLock::new(self.user_ns()),
         }
     }
 }
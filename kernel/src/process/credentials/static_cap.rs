@@ -5,7 +5,7 @@ use aster_rights_proc::require;
 use ostd::sync::{PreemptDisabled, RwLockReadGuard, RwLockWriteGuard};
 
 use super::{capabilities::CapSet, credentials_::Credentials_, Credentials, Gid, Uid};
-use crate::prelude::*;
+use crate::{prelude::*, process::namespace::user_ns::UserNamespace};
 
 impl<R: TRights> Credentials<R> {
     /// Creates a root `Credentials`. This method can only be used when creating the first process
@@ -85,13 +85,14 @@ impl<R: TRights> Credentials<R> {
         self.0.keep_capabilities()
     }
 
-    /// Sets uid. If self is privileged, sets the effective, real, saved-set user ids as `uid`,
-    /// Otherwise, sets effective user id as `uid`.
+    /// Sets uid. If self has the `CAP_SETUID` capability, sets the effective, real, saved-set
+    /// user ids as `uid`. Otherwise, sets effective user id as `uid`, which must be one of the
+    /// real, effective, saved-set user ids.
     ///
     /// This method requires the `Write` right.
     #[require(R > Write)]
-    pub fn set_uid(&self, uid: Uid) {
-        self.0.set_uid(uid);
+    pub fn set_uid(&self, uid: Uid) -> Result<()> {
+        self.0.set_uid(uid)
     }
 
     /// Sets real, effective user ids as `ruid`, `euid` respectively. if `ruid` or `euid`
@@ -187,13 +188,14 @@ impl<R: TRights> Credentials<R> {
         self.0.fsgid()
     }
 
-    /// Sets gid. If self is privileged, sets the effective, real, saved-set group ids as `gid`,
-    /// Otherwise, sets effective group id as `gid`.
+    /// Sets gid. If self has the `CAP_SETGID` capability, sets the effective, real, saved-set
+    /// group ids as `gid`. Otherwise, sets effective group id as `gid`, which must be one of the
+    /// real, effective, saved-set group ids.
     ///
     /// This method requires the `Write` right.
     #[require(R > Write)]
-    pub fn set_gid(&self, gid: Gid) {
-        self.0.set_gid(gid);
+    pub fn set_gid(&self, gid: Gid) -> Result<()> {
+        self.0.set_gid(gid)
     }
 
     /// Sets real, effective group ids as `rgid`, `egid` respectively. if `rgid` or `egid`
@@ -265,6 +267,32 @@ impl<R: TRights> Credentials<R> {
         self.0.groups_mut()
     }
 
+    /// Returns whether `gid` is the file system group id or one of the supplementary group ids.
+    ///
+    /// This method requires the `Read` right.
+    #[require(R > Read)]
+    pub fn is_in_group(&self, gid: Gid) -> bool {
+        self.0.is_in_group(gid)
+    }
+
+    // *********** User namespace methods **********
+
+    /// Gets the user namespace.
+    ///
+    /// This method requires the `Read` right.
+    #[require(R > Read)]
+    pub fn user_ns(&self) -> Arc<UserNamespace> {
+        self.0.user_ns()
+    }
+
+    /// Moves to a new child user namespace, where all the capabilities are gained.
+    ///
+    /// This method requires the `Write` right.
+    #[require(R > Write)]
+    pub fn enter_new_user_ns(&self) -> Result<()> {
+        self.0.enter_new_user_ns()
+    }
+
     // *********** Linux Capability methods **********
 
     /// Gets the capabilities that child process can inherit.
@@ -291,6 +319,39 @@ impl<R: TRights> Credentials<R> {
         self.0.effective_capset()
     }
 
+    /// Returns whether the capability is in effect for the initial user namespace, which owns
+    /// the resources that are not isolated by namespaces.
+    ///
+    /// This method requires the `Read` right.
+    #[require(R > Read)]
+    pub fn has_capability(&self, capability: CapSet) -> bool {
+        self.0
+            .has_capability_in(capability, UserNamespace::get_init_singleton())
+    }
+
+    /// Returns whether the capability is in effect for the user namespace.
+    ///
+    /// This method requires the `Read` right.
+    #[require(R > Read)]
+    pub fn has_capability_in(&self, capability: CapSet, user_ns: &UserNamespace) -> bool {
+        self.0.has_capability_in(capability, user_ns)
+    }
+
+    /// Returns whether the capability is in effect for an object (e.g., a file) with the owner
+    /// and the group.
+    ///
+    /// The capability is checked in the own user namespace, where the owner and the group must be
+    /// mapped.
+    ///
+    /// This method requires the `Read` right.
+    #[require(R > Read)]
+    pub fn has_capability_for_owner(&self, capability: CapSet, owner: Uid, group: Gid) -> bool {
+        let user_ns = self.0.user_ns();
+        self.0.has_capability(capability)
+            && user_ns.uid_from_kernel(owner).is_some()
+            && user_ns.gid_from_kernel(group).is_some()
+    }
+
     /// Sets the capabilities that child process can inherit.
     ///
     /// This method requires the `Write` right.
@@ -314,4 +375,13 @@ impl<R: TRights> Credentials<R> {
     pub fn set_effective_capset(&self, effective_capset: CapSet) {
         self.0.set_effective_capset(effective_capset);
     }
+
+    /// Recomputes the capabilities according to the user ids. This method should only be used
+    /// when executing a new executable file.
+    ///
+    /// This method requires the `Write` right.
+    #[require(R > Write)]
+    pub fn update_capabilities_for_exec(&self) {
+        self.0.update_capabilities_for_exec();
+    }
 }
@@ -2,8 +2,15 @@
 
 use core::sync::atomic::Ordering;
 
-use super::{process_table, Pid, Process};
-use crate::{prelude::*, process::signal::signals::kernel::KernelSignal};
+use super::{process_table, ptrace::exit_tracer, Pid, Process, ProcessGroup};
+use crate::{
+    ipc::shm::collect_destroyed_shm,
+    prelude::*,
+    process::signal::{
+        constants::{SIGCONT, SIGHUP},
+        signals::kernel::KernelSignal,
+    },
+};
 
 /// Exits the current POSIX process.
 ///
@@ -18,10 +25,24 @@ pub(super) fn exit_process(current_process: &Process) {
 
     // Drop fields in `Process`.
     current_process.lock_root_vmar().set_vmar(None);
+    // The shared memory segments detached by dropping the VMAR may need to be destroyed.
+    collect_destroyed_shm();
+    current_process.timer_manager().clear_posix_timers();
 
     send_parent_death_signal(current_process);
 
+    release_controlling_terminal(current_process);
+
+    exit_tracer(current_process);
+
+    let children: Vec<_> = current_process
+        .children()
+        .lock()
+        .values()
+        .cloned()
+        .collect();
     move_children_to_reaper_process(current_process);
+    kill_orphaned_process_groups(current_process, &children);
 
     send_child_death_signal(current_process);
 }
@@ -43,6 +64,80 @@ fn send_parent_death_signal(current_process: &Process) {
     }
 }
 
+/// Releases the controlling terminal if `current_process` is a session leader.
+///
+/// The foreground process group of the terminal will receive `SIGHUP` and `SIGCONT`.
+fn release_controlling_terminal(current_process: &Process) {
+    let Some(session) = current_process
+        .process_group
+        .lock()
+        .upgrade()
+        .and_then(|process_group| process_group.session())
+    else {
+        return;
+    };
+    if session.sid() != current_process.pid() {
+        return;
+    }
+
+    let Some(terminal) = session.lock().terminal().cloned() else {
+        return;
+    };
+    let _ = terminal.unset_control(current_process);
+}
+
+/// Sends `SIGHUP` and `SIGCONT` to the process groups that become orphaned due to the exit.
+///
+/// A process group can become orphaned if it is the process group of `current_process` or one of
+/// its `children`. If such an orphaned process group has stopped members, no job-control shell
+/// will be able to resume them. So POSIX requires that every member of the process group should
+/// receive the signals.
+fn kill_orphaned_process_groups(current_process: &Process, children: &[Arc<Process>]) {
+    let parent = current_process.parent().lock().process().upgrade();
+    let candidates = parent
+        .iter()
+        .map(|parent| (current_process, parent.as_ref()))
+        .chain(
+            children
+                .iter()
+                .map(|child| (child.as_ref(), current_process)),
+        );
+
+    let mut visited_groups: Vec<Arc<ProcessGroup>> = Vec::new();
+    for (process, parent) in candidates {
+        let Some(process_group) = process.process_group.lock().upgrade() else {
+            continue;
+        };
+        let Some(parent_group) = parent.process_group.lock().upgrade() else {
+            continue;
+        };
+
+        // Only the parent that is in a different process group of the same session can prevent
+        // the process group from being orphaned.
+        if Arc::ptr_eq(&process_group, &parent_group)
+            || !process_group
+                .session()
+                .zip(parent_group.session())
+                .is_some_and(|(session, parent_session)| Arc::ptr_eq(&session, &parent_session))
+        {
+            continue;
+        }
+
+        if visited_groups
+            .iter()
+            .any(|visited_group| Arc::ptr_eq(visited_group, &process_group))
+        {
+            continue;
+        }
+
+        if process_group.is_orphaned() && process_group.has_stopped_members() {
+            process_group.broadcast_signal(KernelSignal::new(SIGHUP));
+            process_group.broadcast_signal(KernelSignal::new(SIGCONT));
+        }
+        visited_groups.push(process_group);
+    }
+}
+
 /// Finds a reaper process for `current_process`.
 ///
 /// If there is no reaper process for `current_process`, returns `None`.
@@ -128,7 +223,7 @@ fn send_child_death_signal(current_process: &Process) {
 const INIT_PROCESS_PID: Pid = 1;
 
 /// Gets the init process
-fn get_init_process() -> Option<Arc<Process>> {
+pub(crate) fn get_init_process() -> Option<Arc<Process>> {
     process_table::get_process(INIT_PROCESS_PID)
 }
 
@@ -0,0 +1,108 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use super::posix_thread::AsPosixThread;
+use crate::{prelude::*, thread::Thread};
+
+/// The I/O statistics of a process, which are shown in `/proc/[pid]/io`.
+///
+/// Reference: <https://docs.kernel.org/filesystems/proc.html#proc-pid-io-display-the-io-accounting-fields>
+#[derive(Debug, Default)]
+pub struct IoStats {
+    /// The number of bytes read by the read-like system calls.
+    rchar: AtomicU64,
+    /// The number of bytes written by the write-like system calls.
+    wchar: AtomicU64,
+    /// The number of the successful read-like system calls.
+    syscr: AtomicU64,
+    /// The number of the successful write-like system calls.
+    syscw: AtomicU64,
+    /// The number of bytes fetched from the storage into the page caches.
+    read_bytes: AtomicU64,
+    /// The number of bytes in the page caches that have been dirtied.
+    write_bytes: AtomicU64,
+    /// The number of dirtied bytes that are dropped before being written back, e.g., because the
+    /// file is truncated.
+    cancelled_write_bytes: AtomicU64,
+}
+
+impl IoStats {
+    /// Creates empty I/O statistics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accounts a read-like system call that has read `len` bytes.
+    pub fn account_read(&self, len: usize) {
+        self.rchar.fetch_add(len as u64, Ordering::Relaxed);
+        self.syscr.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Accounts a write-like system call that has written `len` bytes.
+    pub fn account_write(&self, len: usize) {
+        self.wchar.fetch_add(len as u64, Ordering::Relaxed);
+        self.syscw.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Accounts `len` bytes fetched from the storage.
+    pub fn account_storage_read(&self, len: usize) {
+        self.read_bytes.fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    /// Accounts `len` bytes that have been dirtied and will be written to the storage.
+    pub fn account_storage_write(&self, len: usize) {
+        self.write_bytes.fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    /// Accounts `len` dirtied bytes that will no longer be written to the storage.
+    pub fn account_cancelled_write(&self, len: usize) {
+        self.cancelled_write_bytes
+            .fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    pub fn rchar(&self) -> u64 {
+        self.rchar.load(Ordering::Relaxed)
+    }
+
+    pub fn wchar(&self) -> u64 {
+        self.wchar.load(Ordering::Relaxed)
+    }
+
+    pub fn syscr(&self) -> u64 {
+        self.syscr.load(Ordering::Relaxed)
+    }
+
+    pub fn syscw(&self) -> u64 {
+        self.syscw.load(Ordering::Relaxed)
+    }
+
+    pub fn read_bytes(&self) -> u64 {
+        self.read_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn write_bytes(&self) -> u64 {
+        self.write_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn cancelled_write_bytes(&self) -> u64 {
+        self.cancelled_write_bytes.load(Ordering::Relaxed)
+    }
+}
+
+/// Accounts I/O to the process of the current thread.
+///
+/// Nothing is accounted if the current thread is not a POSIX thread (e.g., the writeback
+/// thread) or its process has been reaped, since there is no process to be charged.
+pub fn account_current_io(account: impl FnOnce(&IoStats)) {
+    let Some(thread) = Thread::current() else {
+        return;
+    };
+    let Some(process) = thread
+        .as_posix_thread()
+        .and_then(|posix_thread| posix_thread.weak_process().upgrade())
+    else {
+        return;
+    };
+    account(process.io_stats());
+}
@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::{
+    credentials::capabilities::CapSet,
     posix_thread::{thread_table, AsPosixThread},
     process_table,
     signal::{
@@ -68,6 +69,27 @@ pub fn kill_group(pgid: Pgid, signal: Option<UserSignal>, ctx: &Context) -> Resu
 /// If `signal` is `None`, this method will only check permission without sending
 /// any signal.
 pub fn tgkill(tid: Tid, tgid: Pid, signal: Option<UserSignal>, ctx: &Context) -> Result<()> {
+    kill_thread(tid, Some(tgid), signal, ctx)
+}
+
+/// Sends a signal to a target thread regardless of its thread group, using the
+/// current process as the sender.
+///
+/// This is the obsolete version of [`tgkill`], which is prone to sending the
+/// signal to a wrong thread if the thread ID is recycled.
+///
+/// If `signal` is `None`, this method will only check permission without sending
+/// any signal.
+pub fn tkill(tid: Tid, signal: Option<UserSignal>, ctx: &Context) -> Result<()> {
+    kill_thread(tid, None, signal, ctx)
+}
+
+fn kill_thread(
+    tid: Tid,
+    tgid: Option<Pid>,
+    signal: Option<UserSignal>,
+    ctx: &Context,
+) -> Result<()> {
     let thread = thread_table::get_thread(tid)
         .ok_or_else(|| Error::with_message(Errno::ESRCH, "target thread does not exist"))?;
 
@@ -79,11 +101,8 @@ pub fn tgkill(tid: Tid, tgid: Pid, signal: Option<UserSignal>, ctx: &Context) ->
 
     // Check tgid
     let pid = posix_thread.process().pid();
-    if pid != tgid {
-        return_errno_with_message!(
-            Errno::EINVAL,
-            "the combination of tgid and pid is not valid"
-        );
+    if tgid.is_some_and(|tgid| tgid != pid) {
+        return_errno_with_message!(Errno::ESRCH, "the combination of tgid and pid is not valid");
     }
 
     // Check permission
@@ -164,6 +183,7 @@ fn current_thread_sender_ids(signum: Option<&SigNum>, ctx: &Context) -> SignalSe
     let credentials = ctx.posix_thread.credentials();
     let ruid = credentials.ruid();
     let euid = credentials.euid();
+    let has_kill_capability = credentials.has_capability(CapSet::KILL);
     let sid = signum.and_then(|signum| {
         if *signum == SIGCONT {
             Some(ctx.process.sid())
@@ -172,21 +192,28 @@ fn current_thread_sender_ids(signum: Option<&SigNum>, ctx: &Context) -> SignalSe
         }
     });
 
-    SignalSenderIds::new(ruid, euid, sid)
+    SignalSenderIds::new(ruid, euid, has_kill_capability, sid)
 }
 
 /// The ids of the signal sender process.
 ///
-/// This struct now includes effective user id, real user id and session id.
+/// This struct now includes effective user id, real user id, whether the sender has the
+/// `CAP_KILL` capability, and session id.
 pub(super) struct SignalSenderIds {
     ruid: Uid,
     euid: Uid,
+    has_kill_capability: bool,
     sid: Option<Sid>,
 }
 
 impl SignalSenderIds {
-    fn new(ruid: Uid, euid: Uid, sid: Option<Sid>) -> Self {
-        Self { ruid, euid, sid }
+    fn new(ruid: Uid, euid: Uid, has_kill_capability: bool, sid: Option<Sid>) -> Self {
+        Self {
+            ruid,
+            euid,
+            has_kill_capability,
+            sid,
+        }
     }
 
     pub(super) fn ruid(&self) -> Uid {
@@ -197,6 +224,10 @@ impl SignalSenderIds {
         self.euid
     }
 
+    pub(super) fn has_kill_capability(&self) -> bool {
+        self.has_kill_capability
+    }
+
     pub(super) fn sid(&self) -> Option<Sid> {
         self.sid
     }
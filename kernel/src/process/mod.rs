@@ -1,9 +1,12 @@
 // SPDX-License-Identifier: MPL-2.0
 
+pub mod cgroup;
 mod clone;
 pub mod credentials;
 mod exit;
+mod io_stats;
 mod kill;
+pub mod namespace;
 pub mod posix_thread;
 #[expect(clippy::module_inception)]
 mod process;
@@ -11,7 +14,9 @@ mod process_filter;
 pub mod process_table;
 mod process_vm;
 mod program_loader;
+pub mod ptrace;
 pub mod rlimit;
+pub mod seccomp;
 pub mod signal;
 mod status;
 pub mod sync;
@@ -21,19 +26,19 @@ mod wait;
 
 pub use clone::{clone_child, CloneArgs, CloneFlags};
 pub use credentials::{Credentials, Gid, Uid};
-pub use kill::{kill, kill_all, kill_group, tgkill};
+pub(crate) use exit::get_init_process;
+pub use io_stats::{account_current_io, IoStats};
+pub use kill::{kill, kill_all, kill_group, tgkill, tkill};
 pub use process::{
     spawn_init_process, ExitCode, JobControl, Pgid, Pid, Process, ProcessGroup, Session, Sid,
     Terminal,
 };
 pub use process_filter::ProcessFilter;
-pub use process_vm::{
-    renew_vm_and_map, MAX_ARGV_NUMBER, MAX_ARG_LEN, MAX_ENVP_NUMBER, MAX_ENV_LEN,
-};
+pub use process_vm::{renew_vm, MAX_ARGV_NUMBER, MAX_ARG_LEN, MAX_ENVP_NUMBER, MAX_ENV_LEN};
 pub use program_loader::{check_executable_file, ProgramToLoad};
 pub use rlimit::ResourceType;
 pub use term_status::TermStatus;
-pub use wait::{wait_child_exit, WaitOptions};
+pub use wait::{wait_child_exit, WaitOptions, WaitStatus};
 
 pub(super) fn init() {
     process::init();
@@ -0,0 +1,495 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! User namespaces.
+//!
+//! A user namespace isolates the user IDs, the group IDs, and the capabilities. The IDs in the
+//! credentials are always the IDs in the initial namespace (i.e., the kernel IDs). They are
+//! converted from and to the IDs in the user namespace of the current thread when they cross the
+//! user-kernel boundary, by the uid map and the gid map of the namespace. A kernel ID that is not
+//! mapped is seen as the overflow ID, and an ID that is not mapped cannot be used.
+//!
+//! A new user namespace is created by `unshare(CLONE_NEWUSER)` or `clone(CLONE_NEWUSER)`, and
+//! the creator gains all the capabilities in it. The capabilities in a user namespace are only in
+//! effect for the namespace and its descendants, which is checked by
+//! [`Credentials::has_capability_in`]. The maps are empty until they are written in
+//! `/proc/[pid]/uid_map` and `/proc/[pid]/gid_map`, each of which can only be written once.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man7/user_namespaces.7.html>
+//!
+//! [`Credentials::has_capability_in`]: crate::process::credentials::Credentials::has_capability_in
+
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use aster_rights::ReadOp;
+use spin::Once;
+
+use crate::{
+    prelude::*,
+    process::{credentials::capabilities::CapSet, Credentials, Gid, Uid},
+};
+
+/// A user namespace.
+pub struct UserNamespace {
+    parent: Option<Arc<UserNamespace>>,
+    /// The nesting level, which is zero for the initial namespace.
+    level: usize,
+    /// The effective user ID of the creator.
+    owner: Uid,
+    /// The effective group ID of the creator.
+    group: Gid,
+    uid_map: Once<IdMap>,
+    gid_map: Once<IdMap>,
+    /// Whether `setgroups` is allowed in the namespace, which can only be changed before the gid
+    /// map is written.
+    is_setgroups_allowed: AtomicBool,
+    /// The lock that serializes the writes of the maps and the `setgroups` flag.
+    write_lock: Mutex<()>,
+}
+
+/// The ID that an unmapped kernel ID is seen as.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/linux/highuid.h#L43>
+pub const OVERFLOW_ID: u32 = 65534;
+
+/// The maximum nesting level of the user namespaces.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/kernel/user_namespace.c#L88>
+const MAX_LEVEL: usize = 32;
+
+/// The maximum number of the extents in a map.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/linux/user_namespace.h#L15>
+const MAX_EXTENTS: usize = 340;
+
+impl UserNamespace {
+    /// Returns the initial user namespace, where all the IDs are mapped to themselves.
+    pub fn get_init_singleton() -> &'static Arc<UserNamespace> {
+        static INIT: Once<Arc<UserNamespace>> = Once::new();
+
+        INIT.call_once(|| {
+            let identity = IdMap {
+                extents: vec![IdMapExtent {
+                    first: 0,
+                    lower_first: 0,
+                    count: u32::MAX,
+                }],
+            };
+            Arc::new(Self {
+                parent: None,
+                level: 0,
+                owner: Uid::new_root(),
+                group: Gid::new_root(),
+                uid_map: Once::initialized(identity.clone()),
+                gid_map: Once::initialized(identity),
+                is_setgroups_allowed: AtomicBool::new(true),
+                write_lock: Mutex::new(()),
+            })
+        })
+    }
+
+    /// Creates a child user namespace, whose owner is the user with the effective IDs.
+    ///
+    /// The effective IDs must be mapped in this namespace.
+    pub fn new_child(self: &Arc<Self>, owner: Uid, group: Gid) -> Result<Arc<Self>> {
+        if self.level >= MAX_LEVEL {
+            return_errno_with_message!(Errno::EUSERS, "too many nested user namespaces");
+        }
+        if self.uid_from_kernel(owner).is_none() || self.gid_from_kernel(group).is_none() {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "the effective IDs are not mapped in the user namespace"
+            );
+        }
+
+        Ok(Arc::new(Self {
+            parent: Some(self.clone()),
+            level: self.level + 1,
+            owner,
+            group,
+            uid_map: Once::new(),
+            gid_map: Once::new(),
+            // Like Linux, `setgroups` stays denied in the descendants once it is denied.
+            is_setgroups_allowed: AtomicBool::new(self.is_setgroups_allowed()),
+            write_lock: Mutex::new(()),
+        }))
+    }
+
+    /// Returns the parent namespace, or `None` if this is the initial namespace.
+    pub fn parent(&self) -> Option<&Arc<UserNamespace>> {
+        self.parent.as_ref()
+    }
+
+    /// Returns the nesting level, which is zero for the initial namespace.
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    /// Returns the effective user ID of the creator.
+    pub fn owner(&self) -> Uid {
+        self.owner
+    }
+
+    /// Returns the effective group ID of the creator.
+    pub fn group(&self) -> Gid {
+        self.group
+    }
+
+    //  ******* ID mapping methods *******
+
+    /// Converts a user ID in this namespace to the kernel user ID.
+    pub fn uid_to_kernel(&self, uid: u32) -> Result<Uid> {
+        self.uid_map
+            .get()
+            .and_then(|map| map.map_down(uid))
+            .map(Uid::new)
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the uid is not mapped"))
+    }
+
+    /// Converts a kernel user ID to the user ID in this namespace, or returns `None` if it is not
+    /// mapped.
+    pub fn uid_from_kernel(&self, uid: Uid) -> Option<u32> {
+        self.uid_map.get()?.map_up(uid.into())
+    }
+
+    /// Converts a kernel user ID to the user ID in this namespace, or to [`OVERFLOW_ID`] if it is
+    /// not mapped.
+    pub fn uid_from_kernel_or_overflow(&self, uid: Uid) -> u32 {
+        self.uid_from_kernel(uid).unwrap_or(OVERFLOW_ID)
+    }
+
+    /// Converts a group ID in this namespace to the kernel group ID.
+    pub fn gid_to_kernel(&self, gid: u32) -> Result<Gid> {
+        self.gid_map
+            .get()
+            .and_then(|map| map.map_down(gid))
+            .map(Gid::new)
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the gid is not mapped"))
+    }
+
+    /// Converts a kernel group ID to the group ID in this namespace, or returns `None` if it is
+    /// not mapped.
+    pub fn gid_from_kernel(&self, gid: Gid) -> Option<u32> {
+        self.gid_map.get()?.map_up(gid.into())
+    }
+
+    /// Converts a kernel group ID to the group ID in this namespace, or to [`OVERFLOW_ID`] if it
+    /// is not mapped.
+    pub fn gid_from_kernel_or_overflow(&self, gid: Gid) -> u32 {
+        self.gid_from_kernel(gid).unwrap_or(OVERFLOW_ID)
+    }
+
+    //  ******* Map methods *******
+
+    /// Returns the uid map in the format of `/proc/[pid]/uid_map`.
+    pub fn uid_map_to_string(&self) -> String {
+        self.map_to_string(&self.uid_map, |ns, id| ns.uid_from_kernel(Uid::new(id)))
+    }
+
+    /// Returns the gid map in the format of `/proc/[pid]/gid_map`.
+    pub fn gid_map_to_string(&self) -> String {
+        self.map_to_string(&self.gid_map, |ns, id| ns.gid_from_kernel(Gid::new(id)))
+    }
+
+    /// Writes the uid map, where the lower IDs are the user IDs in the parent namespace.
+    ///
+    /// Without the `CAP_SETUID` capability in the parent namespace, the writer can only map its
+    /// own effective user ID, and only if it is the owner of the namespace.
+    pub fn write_uid_map(&self, data: &str, writer: &Credentials<ReadOp>) -> Result<()> {
+        let parent = self.parent_for_write(writer)?;
+        let map = IdMap::parse(data, parent.uid_map.get())?;
+
+        let is_self_mapping = map
+            .single_id()
+            .is_some_and(|id| Uid::new(id) == writer.euid() && writer.euid() == self.owner);
+        if !is_self_mapping && !writer.has_capability_in(CapSet::SETUID, parent) {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "mapping other uids requires CAP_SETUID in the parent namespace"
+            );
+        }
+
+        let _guard = self.write_lock.lock();
+        set_map_once(&self.uid_map, map)
+    }
+
+    /// Writes the gid map, where the lower IDs are the group IDs in the parent namespace.
+    ///
+    /// Without the `CAP_SETGID` capability in the parent namespace, the writer can only map its
+    /// own effective group ID, and only if `setgroups` is denied in the namespace.
+    pub fn write_gid_map(&self, data: &str, writer: &Credentials<ReadOp>) -> Result<()> {
+        let parent = self.parent_for_write(writer)?;
+        let map = IdMap::parse(data, parent.gid_map.get())?;
+
+        let _guard = self.write_lock.lock();
+
+        // Otherwise, an unprivileged user could drop its supplementary groups with `setgroups`
+        // to bypass the permissions that deny access to the groups.
+        let is_self_mapping = map
+            .single_id()
+            .is_some_and(|id| Gid::new(id) == writer.egid() && !self.is_setgroups_allowed());
+        if !is_self_mapping && !writer.has_capability_in(CapSet::SETGID, parent) {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "mapping other gids requires CAP_SETGID in the parent namespace"
+            );
+        }
+
+        set_map_once(&self.gid_map, map)
+    }
+
+    /// Returns whether `setgroups` is allowed in the namespace.
+    ///
+    /// Even if it is allowed, `setgroups` fails until the gid map is written.
+    pub fn is_setgroups_allowed(&self) -> bool {
+        self.is_setgroups_allowed.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether `setgroups` can be called in the namespace.
+    pub fn may_setgroups(&self) -> bool {
+        self.gid_map.is_completed() && self.is_setgroups_allowed()
+    }
+
+    /// Allows or denies `setgroups` in the namespace, which can only be done before the gid map
+    /// is written. Once it is denied, it cannot be allowed again.
+    pub fn set_setgroups_allowed(&self, is_allowed: bool) -> Result<()> {
+        let _guard = self.write_lock.lock();
+
+        if self.gid_map.is_completed() {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "setgroups cannot be changed after the gid map is written"
+            );
+        }
+        if is_allowed && !self.is_setgroups_allowed() {
+            return_errno_with_message!(Errno::EPERM, "setgroups cannot be allowed once denied");
+        }
+
+        self.is_setgroups_allowed
+            .store(is_allowed, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Returns the parent namespace, where the writer must be if it is not in this namespace.
+    fn parent_for_write(&self, writer: &Credentials<ReadOp>) -> Result<&Arc<UserNamespace>> {
+        let Some(parent) = self.parent.as_ref() else {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "the maps of the initial namespace cannot be written"
+            );
+        };
+
+        let writer_ns = writer.user_ns();
+        if !core::ptr::eq(writer_ns.as_ref(), self) && !Arc::ptr_eq(&writer_ns, parent) {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "the writer is not in the namespace or its parent"
+            );
+        }
+
+        Ok(parent)
+    }
+
+    fn map_to_string(
+        &self,
+        map: &Once<IdMap>,
+        to_parent_id: impl Fn(&UserNamespace, u32) -> Option<u32>,
+    ) -> String {
+        let mut output = String::new();
+        let Some(map) = map.get() else {
+            return output;
+        };
+
+        for extent in map.extents.iter() {
+            let lower_first = match self.parent.as_ref() {
+                Some(parent) => to_parent_id(parent, extent.lower_first).unwrap_or(OVERFLOW_ID),
+                None => extent.lower_first,
+            };
+            writeln!(
+                output,
+                "{:>10} {:>10} {:>10}",
+                extent.first, lower_first, extent.count
+            )
+            .unwrap();
+        }
+        output
+    }
+}
+
+fn set_map_once(map_once: &Once<IdMap>, map: IdMap) -> Result<()> {
+    if map_once.is_completed() {
+        return_errno_with_message!(Errno::EPERM, "the map can only be written once");
+    }
+    map_once.call_once(|| map);
+    Ok(())
+}
+
+/// A map from the IDs in a user namespace to the kernel IDs.
+#[derive(Debug, Clone)]
+struct IdMap {
+    extents: Vec<IdMapExtent>,
+}
+
+/// A range of the IDs that are mapped to a range of the kernel IDs.
+#[derive(Debug, Clone, Copy)]
+struct IdMapExtent {
+    /// The first ID in the user namespace.
+    first: u32,
+    /// The first kernel ID.
+    lower_first: u32,
+    count: u32,
+}
+
+impl IdMapExtent {
+    fn overlaps(&self, other: &IdMapExtent) -> bool {
+        let ranges_overlap = |a: u32, b: u32| a < b + other.count && b < a + self.count;
+        ranges_overlap(self.first, other.first)
+            || ranges_overlap(self.lower_first, other.lower_first)
+    }
+}
+
+impl IdMap {
+    /// Parses the lines of `<first> <lower_first> <count>`, where the lower IDs are the IDs in
+    /// the parent namespace, whose map is `parent_map`.
+    ///
+    /// Like Linux, the ranges of the IDs and the ranges of the lower IDs must not overlap, and a
+    /// range of the lower IDs must be in one extent of the parent map.
+    fn parse(data: &str, parent_map: Option<&IdMap>) -> Result<Self> {
+        let invalid = || Error::with_message(Errno::EINVAL, "the map is malformed");
+
+        let mut extents: Vec<IdMapExtent> = Vec::new();
+        for line in data.lines().filter(|line| !line.trim().is_empty()) {
+            let mut fields = line.split_whitespace().map(str::parse::<u32>);
+            let (Some(Ok(first)), Some(Ok(lower_first)), Some(Ok(count)), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(invalid());
+            };
+            if count == 0
+                || first.checked_add(count).is_none()
+                || lower_first.checked_add(count).is_none()
+            {
+                return Err(invalid());
+            }
+
+            let new_extent = IdMapExtent {
+                first,
+                lower_first,
+                count,
+            };
+            if extents.len() == MAX_EXTENTS
+                || extents.iter().any(|extent| extent.overlaps(&new_extent))
+            {
+                return Err(invalid());
+            }
+            extents.push(new_extent);
+        }
+        if extents.is_empty() {
+            return Err(invalid());
+        }
+
+        for extent in extents.iter_mut() {
+            extent.lower_first = parent_map
+                .and_then(|parent_map| parent_map.map_range_down(extent.lower_first, extent.count))
+                .ok_or_else(|| {
+                    Error::with_message(Errno::EPERM, "the lower IDs are not mapped in the parent")
+                })?;
+        }
+        Ok(Self { extents })
+    }
+
+    /// Returns the lower ID if the map has only one ID.
+    fn single_id(&self) -> Option<u32> {
+        match self.extents.as_slice() {
+            [extent] if extent.count == 1 => Some(extent.lower_first),
+            _ => None,
+        }
+    }
+
+    fn map_down(&self, id: u32) -> Option<u32> {
+        self.map_range_down(id, 1)
+    }
+
+    /// Maps a range of the IDs, which must be in one extent, to the first lower ID.
+    fn map_range_down(&self, first: u32, count: u32) -> Option<u32> {
+        self.extents
+            .iter()
+            .find(|extent| {
+                first >= extent.first
+                    && count <= extent.count
+                    && first - extent.first <= extent.count - count
+            })
+            .map(|extent| extent.lower_first + (first - extent.first))
+    }
+
+    fn map_up(&self, id: u32) -> Option<u32> {
+        self.extents
+            .iter()
+            .find(|extent| id.wrapping_sub(extent.lower_first) < extent.count)
+            .map(|extent| extent.first + (id - extent.lower_first))
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    fn parent_map() -> IdMap {
+        IdMap {
+            extents: vec![IdMapExtent {
+                first: 0,
+                lower_first: 1000,
+                count: 10,
+            }],
+        }
+    }
+
+    #[ktest]
+    fn parse_map() {
+        let identity = UserNamespace::get_init_singleton().uid_map.get();
+
+        let map = IdMap::parse("0 1000 1\n1 100000 65536\n", identity).unwrap();
+        assert_eq!(map.map_down(0), Some(1000));
+        assert_eq!(map.map_down(2), Some(100001));
+        assert_eq!(map.map_down(65537), None);
+        assert_eq!(map.map_up(1000), Some(0));
+        assert_eq!(map.map_up(100000), Some(1));
+        assert_eq!(map.map_up(0), None);
+        assert_eq!(map.single_id(), None);
+
+        let map = IdMap::parse("0 1000 1", identity).unwrap();
+        assert_eq!(map.single_id(), Some(1000));
+    }
+
+    #[ktest]
+    fn parse_invalid_map() {
+        let identity = UserNamespace::get_init_singleton().uid_map.get();
+
+        assert!(IdMap::parse("", identity).is_err());
+        assert!(IdMap::parse("0 1000", identity).is_err());
+        assert!(IdMap::parse("0 1000 0", identity).is_err());
+        assert!(IdMap::parse("0 1000 1 1", identity).is_err());
+        assert!(IdMap::parse("0 4294967295 2", identity).is_err());
+        // The IDs overlap.
+        assert!(IdMap::parse("0 1000 10\n5 2000 1", identity).is_err());
+        // The lower IDs overlap.
+        assert!(IdMap::parse("0 1000 10\n20 1005 1", identity).is_err());
+        // The parent map is not written.
+        assert!(IdMap::parse("0 1000 1", None).is_err());
+    }
+
+    #[ktest]
+    fn map_lower_ids() {
+        let parent_map = parent_map();
+
+        let map = IdMap::parse("0 5 5", Some(&parent_map)).unwrap();
+        assert_eq!(map.map_down(0), Some(1005));
+        assert_eq!(map.map_up(1009), Some(4));
+        assert!(IdMap::parse("0 5 6", Some(&parent_map)).is_err());
+        assert!(IdMap::parse("0 10 1", Some(&parent_map)).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use spin::Once;
+
+use crate::{fs::procfs::ProcPollEvent, prelude::*};
+
+/// The UTS namespace.
+///
+/// Namespaces are not supported yet, so there is only one UTS namespace (i.e., the initial
+/// namespace) that is shared by all processes.
+pub struct UtsNamespace {
+    uts_name: RwLock<UtsName>,
+    hostname_event: ProcPollEvent,
+    domainname_event: ProcPollEvent,
+}
+
+impl UtsNamespace {
+    /// Returns the initial UTS namespace.
+    pub fn get_init_singleton() -> &'static Arc<UtsNamespace> {
+        static INIT: Once<Arc<UtsNamespace>> = Once::new();
+
+        INIT.call_once(|| {
+            Arc::new(Self {
+                uts_name: RwLock::new(UtsName::new_init()),
+                hostname_event: ProcPollEvent::new(),
+                domainname_event: ProcPollEvent::new(),
+            })
+        })
+    }
+
+    /// Returns a copy of the UTS name.
+    pub fn uts_name(&self) -> UtsName {
+        *self.uts_name.read()
+    }
+
+    /// Sets the host name.
+    pub fn set_hostname(&self, name: &[u8]) -> Result<()> {
+        copy_field(name, &mut self.uts_name.write().nodename)?;
+        self.hostname_event.notify();
+        Ok(())
+    }
+
+    /// Sets the NIS domain name.
+    pub fn set_domainname(&self, name: &[u8]) -> Result<()> {
+        copy_field(name, &mut self.uts_name.write().domainname)?;
+        self.domainname_event.notify();
+        Ok(())
+    }
+
+    /// Returns the change notification of the host name.
+    pub fn hostname_event(&self) -> &ProcPollEvent {
+        &self.hostname_event
+    }
+
+    /// Returns the change notification of the NIS domain name.
+    pub fn domainname_event(&self) -> &ProcPollEvent {
+        &self.domainname_event
+    }
+}
+
+/// The maximum length of a UTS field, excluding the trailing null byte.
+pub const UTS_NAME_MAX_LEN: usize = 64;
+
+const UTS_FIELD_LEN: usize = UTS_NAME_MAX_LEN + 1;
+
+/// The UTS name, which has the same layout as Linux's `struct new_utsname`.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub struct UtsName {
+    sysname: [u8; UTS_FIELD_LEN],
+    nodename: [u8; UTS_FIELD_LEN],
+    release: [u8; UTS_FIELD_LEN],
+    version: [u8; UTS_FIELD_LEN],
+    machine: [u8; UTS_FIELD_LEN],
+    domainname: [u8; UTS_FIELD_LEN],
+}
+
+impl UtsName {
+    // We don't use the real name and version of our OS here. Instead, we pick up fake values that
+    // are the same as the ones of Linux. The values are used to fool glibc since glibc will check
+    // the version and the OS name.
+    fn new_init() -> Self {
+        let mut uts_name = Self::new_zeroed();
+
+        let copy_slice = |src: &[u8], dst: &mut [u8]| {
+            dst[..src.len()].copy_from_slice(src);
+        };
+        copy_slice(b"Linux", &mut uts_name.sysname);
+        copy_slice(b"WHITLEY", &mut uts_name.nodename);
+        copy_slice(b"5.13.0", &mut uts_name.release);
+        copy_slice(b"5.13.0", &mut uts_name.version);
+        copy_slice(MACHINE, &mut uts_name.machine);
+        copy_slice(b"(none)", &mut uts_name.domainname);
+
+        uts_name
+    }
+
+    /// Returns the host name.
+    pub fn hostname(&self) -> &[u8] {
+        field_bytes(&self.nodename)
+    }
+
+    /// Returns the NIS domain name.
+    pub fn domainname(&self) -> &[u8] {
+        field_bytes(&self.domainname)
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+const MACHINE: &[u8] = b"x86_64";
+#[cfg(target_arch = "riscv64")]
+const MACHINE: &[u8] = b"riscv64";
+
+fn copy_field(src: &[u8], dst: &mut [u8; UTS_FIELD_LEN]) -> Result<()> {
+    if src.len() > UTS_NAME_MAX_LEN {
+        return_errno_with_message!(Errno::EINVAL, "the name is too long");
+    }
+
+    dst[..src.len()].copy_from_slice(src);
+    dst[src.len()..].fill(0);
+
+    Ok(())
+}
+
+fn field_bytes(field: &[u8; UTS_FIELD_LEN]) -> &[u8] {
+    let len = field
+        .iter()
+        .position(|&b| b == 0)
+        .unwrap_or(UTS_NAME_MAX_LEN);
+    &field[..len]
+}
@@ -1,5 +1,8 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::sync::atomic::{AtomicBool, AtomicU16};
+
+use aster_block::ioprio::IoPriority;
 use ostd::{
     cpu::{context::UserContext, CpuSet},
     sync::RwArc,
@@ -12,7 +15,9 @@ use crate::{
     prelude::*,
     process::{
         posix_thread::name::ThreadName,
-        signal::{sig_mask::AtomicSigMask, sig_queues::SigQueues},
+        ptrace::Tracee,
+        seccomp::Seccomp,
+        signal::{sig_mask::AtomicSigMask, sig_queues::SigQueues, SigStack},
         Credentials, Process,
     },
     sched::{Nice, SchedPolicy},
@@ -29,6 +34,9 @@ pub struct PosixThreadBuilder {
     credentials: Credentials,
 
     // Optional part
+    no_new_privs: bool,
+    seccomp: Seccomp,
+    io_priority: IoPriority,
     thread_name: Option<ThreadName>,
     set_child_tid: Vaddr,
     clear_child_tid: Vaddr,
@@ -36,6 +44,7 @@ pub struct PosixThreadBuilder {
     fs: Option<Arc<ThreadFsInfo>>,
     sig_mask: AtomicSigMask,
     sig_queues: SigQueues,
+    sig_stack: Option<SigStack>,
     sched_policy: SchedPolicy,
 }
 
@@ -46,6 +55,9 @@ impl PosixThreadBuilder {
             user_ctx,
             process: Weak::new(),
             credentials,
+            no_new_privs: false,
+            seccomp: Seccomp::new(),
+            io_priority: IoPriority::NONE,
             thread_name: None,
             set_child_tid: 0,
             clear_child_tid: 0,
@@ -53,6 +65,7 @@ impl PosixThreadBuilder {
             fs: None,
             sig_mask: AtomicSigMask::new_empty(),
             sig_queues: SigQueues::new(),
+            sig_stack: None,
             sched_policy: SchedPolicy::Fair(Nice::default()),
         }
     }
@@ -62,6 +75,21 @@ impl PosixThreadBuilder {
         self
     }
 
+    pub fn no_new_privs(mut self, no_new_privs: bool) -> Self {
+        self.no_new_privs = no_new_privs;
+        self
+    }
+
+    pub fn seccomp(mut self, seccomp: Seccomp) -> Self {
+        self.seccomp = seccomp;
+        self
+    }
+
+    pub fn io_priority(mut self, io_priority: IoPriority) -> Self {
+        self.io_priority = io_priority;
+        self
+    }
+
     pub fn thread_name(mut self, thread_name: Option<ThreadName>) -> Self {
         self.thread_name = thread_name;
         self
@@ -92,12 +120,20 @@ impl PosixThreadBuilder {
         self
     }
 
+    pub fn sig_stack(mut self, sig_stack: Option<SigStack>) -> Self {
+        self.sig_stack = sig_stack;
+        self
+    }
+
     pub fn build(self) -> Arc<Task> {
         let Self {
             tid,
             user_ctx,
             process,
             credentials,
+            no_new_privs,
+            seccomp,
+            io_priority,
             thread_name,
             set_child_tid,
             clear_child_tid,
@@ -105,6 +141,7 @@ impl PosixThreadBuilder {
             fs,
             sig_mask,
             sig_queues,
+            sig_stack,
             sched_policy,
         } = self;
 
@@ -112,13 +149,11 @@ impl PosixThreadBuilder {
 
         let fs = fs.unwrap_or_else(|| Arc::new(ThreadFsInfo::default()));
 
-        let root_vmar = process
-            .upgrade()
-            .unwrap()
-            .lock_root_vmar()
-            .unwrap()
-            .dup()
-            .unwrap();
+        let (root_vmar, cgroup) = {
+            let process = process.upgrade().unwrap();
+            let root_vmar = process.lock_root_vmar().unwrap().dup().unwrap();
+            (root_vmar, process.cgroup())
+        };
 
         Arc::new_cyclic(|weak_task| {
             let posix_thread = {
@@ -131,8 +166,13 @@ impl PosixThreadBuilder {
                     tid,
                     name: Mutex::new(thread_name),
                     credentials,
+                    no_new_privs: AtomicBool::new(no_new_privs),
+                    seccomp,
+                    io_priority: AtomicU16::new(io_priority.to_raw()),
+                    tracee: Tracee::new(),
+                    is_frozen: AtomicBool::new(false),
                     file_table: Mutex::new(Some(file_table.clone_ro())),
-                    fs,
+                    fs: RwMutex::new(fs),
                     sig_mask,
                     sig_queues,
                     signalled_waker: SpinLock::new(None),
@@ -149,9 +189,17 @@ impl PosixThreadBuilder {
                 cpu_affinity,
                 sched_policy,
             ));
-
-            let thread_local =
-                ThreadLocal::new(set_child_tid, clear_child_tid, root_vmar, file_table);
+            thread
+                .sched_attr()
+                .set_cpu_bandwidth(Some(cgroup.cpu_bandwidth().clone()));
+
+            let thread_local = ThreadLocal::new(
+                set_child_tid,
+                clear_child_tid,
+                root_vmar,
+                file_table,
+                sig_stack,
+            );
 
             thread_table::add_thread(tid, thread.clone());
             task::create_new_user_task(user_ctx, thread, thread_local)
@@ -3,14 +3,16 @@
 use ostd::task::{CurrentTask, Task};
 
 use super::{
-    futex::futex_wake, robust_list::wake_robust_futex, thread_table, AsPosixThread, AsThreadLocal,
-    ThreadLocal,
+    futex::futex_wake,
+    robust_list::{wake_robust_futex, RobustListHead},
+    thread_table, AsPosixThread, AsThreadLocal, ThreadLocal,
 };
 use crate::{
     current_userspace,
     prelude::*,
     process::{
         exit::exit_process,
+        ptrace::exit_tracee,
         signal::{constants::SIGKILL, signals::kernel::KernelSignal},
         task_set::TaskSet,
         TermStatus,
@@ -73,6 +75,8 @@ fn exit_internal(term_status: TermStatus, is_exiting_group: bool) {
 
     wake_robust_list(thread_local, posix_thread.tid());
 
+    exit_tracee(current_thread);
+
     // According to Linux behavior, the main thread shouldn't be removed from the table until the
     // process is reaped by its parent.
     if posix_thread.tid() != posix_process.pid() {
@@ -128,13 +132,20 @@ fn wake_clear_ctid(thread_local: &ThreadLocal) {
 fn wake_robust_list(thread_local: &ThreadLocal, tid: Tid) {
     let mut robust_list = thread_local.robust_list().borrow_mut();
 
-    let list_head = match *robust_list {
-        Some(robust_list_head) => robust_list_head,
-        None => return,
+    let Some(head_ptr) = *robust_list else {
+        return;
+    };
+
+    // The robust list head is read when the thread exits, because the user space may have
+    // modified it after it was registered.
+    let Ok(list_head) = current_userspace!().read_val::<RobustListHead>(head_ptr) else {
+        debug!("exit: cannot read the robust list head at {:#x}", head_ptr);
+        *robust_list = None;
+        return;
     };
 
     trace!("exit: wake up the rubust list: {:?}", list_head);
-    for futex_addr in list_head.futexes() {
+    for futex_addr in list_head.futexes(head_ptr) {
         let _ = wake_robust_futex(futex_addr, tid)
             .inspect_err(|err| debug!("exit: cannot wake up the robust futex: {:?}", err));
     }
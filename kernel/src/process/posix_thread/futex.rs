@@ -2,11 +2,16 @@
 
 use ostd::{
     cpu::num_cpus,
-    sync::{Waiter, Waker},
+    sync::{PreemptDisabled, SpinLockGuard, Waiter, Waker},
 };
 use spin::Once;
 
-use crate::{prelude::*, process::Pid, time::wait::ManagedTimeout};
+use super::thread_table;
+use crate::{
+    prelude::*,
+    process::Pid,
+    time::wait::{ManagedTimeout, TimeoutExt},
+};
 
 type FutexBitSet = u32;
 
@@ -14,6 +19,13 @@ const FUTEX_OP_MASK: u32 = 0x0000_000F;
 const FUTEX_FLAGS_MASK: u32 = 0xFFFF_FFF0;
 const FUTEX_BITSET_MATCH_ANY: FutexBitSet = 0xFFFF_FFFF;
 
+/// The bits in the futex word of a PI futex or a robust futex.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/futex.h#L131>.
+pub(super) const FUTEX_WAITERS: u32 = 0x8000_0000;
+pub(super) const FUTEX_OWNER_DIED: u32 = 0x4000_0000;
+pub(super) const FUTEX_TID_MASK: u32 = 0x3FFF_FFFF;
+
 /// do futex wait
 pub fn futex_wait(
     futex_addr: u64,
@@ -119,54 +131,200 @@ pub fn futex_wake_bitset(
 }
 
 /// Does futex requeue
+///
+/// If `cmp_val` is specified, the futex value is checked against it before any waiter is woken
+/// up or requeued, as `FUTEX_CMP_REQUEUE` requires.
+///
+/// Returns the number of woken waiters and the number of requeued waiters.
 pub fn futex_requeue(
     futex_addr: Vaddr,
     max_nwakes: usize,
     max_nrequeues: usize,
     futex_new_addr: Vaddr,
+    cmp_val: Option<i32>,
+    ctx: &Context,
+    pid: Option<Pid>,
+) -> Result<(usize, usize)> {
+    let futex_key = FutexKey::new(futex_addr, FUTEX_BITSET_MATCH_ANY, pid);
+    let futex_new_key = FutexKey::new(futex_new_addr, FUTEX_BITSET_MATCH_ANY, pid);
+    let (mut futex_bucket, mut futex_new_bucket) = lock_bucket_pair(futex_key, futex_new_key);
+
+    if let Some(cmp_val) = cmp_val
+        && futex_key.load_val(ctx)? != cmp_val
+    {
+        return_errno_with_message!(Errno::EAGAIN, "futex value does not match");
+    }
+
+    let nwakes = futex_bucket.remove_and_wake_items(futex_key, max_nwakes);
+    let nrequeues = if let Some(futex_new_bucket) = futex_new_bucket.as_mut() {
+        futex_bucket.requeue_items_to_another_bucket(
+            futex_key,
+            futex_new_bucket,
+            futex_new_key,
+            max_nrequeues,
+        )
+    } else {
+        futex_bucket.update_item_keys(futex_key, futex_new_key, max_nrequeues)
+    };
+
+    Ok((nwakes, nrequeues))
+}
+
+/// Does futex wake op
+///
+/// The value at `futex_addr2` is updated according to `wake_op`. Then, at most `max_nwakes`
+/// waiters of `futex_addr` are woken up. If the old value at `futex_addr2` satisfies the
+/// comparison in `wake_op`, at most `max_nwakes2` waiters of `futex_addr2` are also woken up.
+///
+/// Returns the total number of woken waiters.
+pub fn futex_wake_op(
+    futex_addr: Vaddr,
+    max_nwakes: usize,
+    futex_addr2: Vaddr,
+    max_nwakes2: usize,
+    wake_op: FutexWakeOp,
+    ctx: &Context,
     pid: Option<Pid>,
 ) -> Result<usize> {
-    if futex_new_addr == futex_addr {
-        return futex_wake(futex_addr, max_nwakes, pid);
+    debug!(
+        "futex_wake_op addr: {:#x}, addr2: {:#x}, wake_op: {:?}",
+        futex_addr, futex_addr2, wake_op
+    );
+
+    let futex_key = FutexKey::new(futex_addr, FUTEX_BITSET_MATCH_ANY, pid);
+    let futex_key2 = FutexKey::new(futex_addr2, FUTEX_BITSET_MATCH_ANY, pid);
+    let (mut futex_bucket, mut futex_bucket2) = lock_bucket_pair(futex_key, futex_key2);
+
+    let user_space = ctx.user_space();
+    let mut old_val = futex_key2.load_val(ctx)?;
+    loop {
+        let new_val = wake_op.calculate_new_val(old_val);
+        let prev_val =
+            user_space.atomic_compare_exchange(futex_addr2, old_val as u32, new_val as u32)? as i32;
+        if prev_val == old_val {
+            break;
+        }
+        // The user space has changed the value concurrently, retry.
+        old_val = prev_val;
     }
 
+    let mut nwakes = futex_bucket.remove_and_wake_items(futex_key, max_nwakes);
+    if wake_op.should_wake(old_val) {
+        let futex_bucket2 = match futex_bucket2.as_mut() {
+            Some(futex_bucket2) => futex_bucket2,
+            None => &mut futex_bucket,
+        };
+        nwakes += futex_bucket2.remove_and_wake_items(futex_key2, max_nwakes2);
+    }
+
+    Ok(nwakes)
+}
+
+/// Does futex lock PI
+///
+/// The owner of a PI futex is recorded in the futex word with its TID, and the `FUTEX_WAITERS`
+/// bit is set if there are waiters, so that the owner has to unlock the futex via
+/// [`futex_unlock_pi`].
+///
+/// If `is_trylock` is true, this function fails with `EAGAIN` instead of waiting for the owner.
+//
+// TODO: Support priority inheritance. Currently, the owner is not boosted to the priority of the
+// waiters.
+pub fn futex_lock_pi(
+    futex_addr: Vaddr,
+    timeout: Option<ManagedTimeout>,
+    is_trylock: bool,
+    ctx: &Context,
+    pid: Option<Pid>,
+) -> Result<()> {
+    debug!(
+        "futex_lock_pi addr: {:#x}, is_trylock: {}",
+        futex_addr, is_trylock
+    );
+
     let futex_key = FutexKey::new(futex_addr, FUTEX_BITSET_MATCH_ANY, pid);
-    let futex_new_key = FutexKey::new(futex_new_addr, FUTEX_BITSET_MATCH_ANY, pid);
-    let (bucket_idx, futex_bucket_ref) = get_futex_bucket(futex_key);
-    let (new_bucket_idx, futex_new_bucket_ref) = get_futex_bucket(futex_new_key);
-
-    let nwakes = {
-        if bucket_idx == new_bucket_idx {
-            let mut futex_bucket = futex_bucket_ref.lock();
-            let nwakes = futex_bucket.remove_and_wake_items(futex_key, max_nwakes);
-            futex_bucket.update_item_keys(futex_key, futex_new_key, max_nrequeues);
-            drop(futex_bucket);
-            nwakes
-        } else {
-            let (mut futex_bucket, mut futex_new_bucket) = {
-                if bucket_idx < new_bucket_idx {
-                    let futex_bucket = futex_bucket_ref.lock();
-                    let futext_new_bucket = futex_new_bucket_ref.lock();
-                    (futex_bucket, futext_new_bucket)
-                } else {
-                    // bucket_idx > new_bucket_idx
-                    let futex_new_bucket = futex_new_bucket_ref.lock();
-                    let futex_bucket = futex_bucket_ref.lock();
-                    (futex_bucket, futex_new_bucket)
-                }
-            };
-
-            let nwakes = futex_bucket.remove_and_wake_items(futex_key, max_nwakes);
-            futex_bucket.requeue_items_to_another_bucket(
-                futex_key,
-                &mut futex_new_bucket,
-                futex_new_key,
-                max_nrequeues,
+    let (_, futex_bucket_ref) = get_futex_bucket(futex_key);
+    let tid = ctx.posix_thread.tid();
+    let timeout = TimeoutExt::from(timeout);
+
+    let user_space = ctx.user_space();
+
+    loop {
+        let mut futex_bucket = futex_bucket_ref.lock();
+
+        // The futex word is updated with compare-and-exchange operations, since the user space
+        // can change it concurrently. If it is changed, the whole procedure is retried.
+        let val = futex_key.load_val(ctx)? as u32;
+        let owner = val & FUTEX_TID_MASK;
+
+        if owner == 0 {
+            // The `FUTEX_OWNER_DIED` bit is kept so that the user space can know that the
+            // previous owner died without unlocking the futex.
+            let mut new_val = tid | (val & FUTEX_OWNER_DIED);
+            if futex_bucket.has_items(futex_key) {
+                new_val |= FUTEX_WAITERS;
+            }
+            if user_space.atomic_compare_exchange(futex_addr, val, new_val)? == val {
+                return Ok(());
+            }
+            continue;
+        }
+
+        if owner == tid {
+            return_errno_with_message!(Errno::EDEADLK, "the futex is locked by the current thread");
+        }
+        if thread_table::get_thread(owner).is_none() {
+            return_errno_with_message!(Errno::ESRCH, "the owner of the futex does not exist");
+        }
+        if is_trylock {
+            return_errno_with_message!(Errno::EAGAIN, "the futex is locked by another thread");
+        }
+
+        if val & FUTEX_WAITERS == 0
+            && user_space.atomic_compare_exchange(futex_addr, val, val | FUTEX_WAITERS)? != val
+        {
+            continue;
+        }
+
+        let (futex_item, waiter) = FutexItem::create(futex_key);
+        futex_bucket.add_item(futex_item);
+        drop(futex_bucket);
+
+        waiter.pause_timeout(&timeout)?;
+    }
+}
+
+/// Does futex unlock PI
+///
+/// The futex is released and one of the waiters is woken up to lock the futex again.
+pub fn futex_unlock_pi(futex_addr: Vaddr, ctx: &Context, pid: Option<Pid>) -> Result<()> {
+    debug!("futex_unlock_pi addr: {:#x}", futex_addr);
+
+    let futex_key = FutexKey::new(futex_addr, FUTEX_BITSET_MATCH_ANY, pid);
+    let (_, futex_bucket_ref) = get_futex_bucket(futex_key);
+    let mut futex_bucket = futex_bucket_ref.lock();
+
+    let user_space = ctx.user_space();
+    let mut val = futex_key.load_val(ctx)? as u32;
+    loop {
+        if val & FUTEX_TID_MASK != ctx.posix_thread.tid() {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "the futex is not locked by the current thread"
             );
-            nwakes
         }
-    };
-    Ok(nwakes)
+
+        let prev_val = user_space.atomic_compare_exchange(futex_addr, val, 0)?;
+        if prev_val == val {
+            break;
+        }
+        // The user space has changed the value concurrently, retry.
+        val = prev_val;
+    }
+
+    futex_bucket.remove_and_wake_items(futex_key, 1);
+
+    Ok(())
 }
 
 static FUTEX_BUCKETS: Once<FutexBucketVec> = Once::new();
@@ -183,6 +341,29 @@ fn get_futex_bucket(key: FutexKey) -> (usize, &'static SpinLock<FutexBucket>) {
     FUTEX_BUCKETS.get().unwrap().get_bucket(key)
 }
 
+type FutexBucketGuard = SpinLockGuard<'static, FutexBucket, PreemptDisabled>;
+
+/// Locks the futex buckets of the two keys.
+///
+/// The buckets are locked in the order of their indexes to avoid deadlocks. If both keys are in
+/// the same bucket, the bucket is locked only once and the second returned guard is `None`.
+fn lock_bucket_pair(key: FutexKey, key2: FutexKey) -> (FutexBucketGuard, Option<FutexBucketGuard>) {
+    let (bucket_idx, futex_bucket_ref) = get_futex_bucket(key);
+    let (bucket_idx2, futex_bucket_ref2) = get_futex_bucket(key2);
+
+    if bucket_idx == bucket_idx2 {
+        (futex_bucket_ref.lock(), None)
+    } else if bucket_idx < bucket_idx2 {
+        let futex_bucket = futex_bucket_ref.lock();
+        let futex_bucket2 = futex_bucket_ref2.lock();
+        (futex_bucket, Some(futex_bucket2))
+    } else {
+        let futex_bucket2 = futex_bucket_ref2.lock();
+        let futex_bucket = futex_bucket_ref.lock();
+        (futex_bucket, Some(futex_bucket2))
+    }
+}
+
 /// Initialize the futex system.
 pub fn init() {
     FUTEX_BUCKETS.call_once(|| FutexBucketVec::new(get_bucket_count()));
@@ -251,17 +432,27 @@ impl FutexBucket {
         count
     }
 
-    pub fn update_item_keys(&mut self, key: FutexKey, new_key: FutexKey, max_count: usize) {
+    pub fn has_items(&self, key: FutexKey) -> bool {
+        self.items.iter().any(|item| item.key.match_up(&key))
+    }
+
+    pub fn update_item_keys(
+        &mut self,
+        key: FutexKey,
+        new_key: FutexKey,
+        max_count: usize,
+    ) -> usize {
         let mut count = 0;
         for item in self.items.iter_mut() {
+            if count >= max_count {
+                break;
+            }
             if item.key.match_up(&key) {
                 item.key = new_key;
                 count += 1;
             }
-            if count >= max_count {
-                break;
-            }
         }
+        count
     }
 
     pub fn requeue_items_to_another_bucket(
@@ -270,7 +461,7 @@ impl FutexBucket {
         another: &mut Self,
         new_key: FutexKey,
         max_nrequeues: usize,
-    ) {
+    ) -> usize {
         let mut count = 0;
         self.items
             .extract_if(.., |item| {
@@ -285,6 +476,7 @@ impl FutexBucket {
                 extracted.key = new_key;
                 another.add_item(extracted);
             });
+        count
     }
 }
 
@@ -389,6 +581,93 @@ impl FutexFlags {
     }
 }
 
+/// The operation encoded in the `val3` argument of `FUTEX_WAKE_OP`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/kernel/futex/waitwake.c#L199>.
+#[derive(Debug, Clone, Copy)]
+pub struct FutexWakeOp {
+    op: FutexWakeOpType,
+    cmp: FutexWakeOpCmp,
+    oparg: u32,
+    cmparg: i32,
+}
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[expect(non_camel_case_types)]
+enum FutexWakeOpType {
+    FUTEX_OP_SET = 0,
+    FUTEX_OP_ADD = 1,
+    FUTEX_OP_OR = 2,
+    FUTEX_OP_ANDN = 3,
+    FUTEX_OP_XOR = 4,
+}
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[expect(non_camel_case_types)]
+enum FutexWakeOpCmp {
+    FUTEX_OP_CMP_EQ = 0,
+    FUTEX_OP_CMP_NE = 1,
+    FUTEX_OP_CMP_LT = 2,
+    FUTEX_OP_CMP_LE = 3,
+    FUTEX_OP_CMP_GT = 4,
+    FUTEX_OP_CMP_GE = 5,
+}
+
+impl FutexWakeOp {
+    /// The flag indicating that `oparg` is a shift count, i.e., `FUTEX_OP_OPARG_SHIFT << 28`.
+    const OPARG_SHIFT: u32 = 0x8000_0000;
+
+    pub fn from_u32(bits: u32) -> Result<Self> {
+        let op = FutexWakeOpType::try_from((bits >> 28) & 0x7)
+            .map_err(|_| Error::with_message(Errno::ENOSYS, "unknown futex wake op"))?;
+        let cmp = FutexWakeOpCmp::try_from((bits >> 24) & 0xF)
+            .map_err(|_| Error::with_message(Errno::ENOSYS, "unknown futex wake op comparison"))?;
+
+        // Both arguments are 12-bit signed integers.
+        let oparg = ((bits << 8) as i32) >> 20;
+        let cmparg = ((bits << 20) as i32) >> 20;
+
+        let oparg = if bits & Self::OPARG_SHIFT != 0 {
+            // Linux also masks the invalid shift counts instead of reporting errors.
+            1 << (oparg & 31)
+        } else {
+            oparg as u32
+        };
+
+        Ok(Self {
+            op,
+            cmp,
+            oparg,
+            cmparg,
+        })
+    }
+
+    fn calculate_new_val(&self, old_val: i32) -> i32 {
+        let old_val = old_val as u32;
+        let new_val = match self.op {
+            FutexWakeOpType::FUTEX_OP_SET => self.oparg,
+            FutexWakeOpType::FUTEX_OP_ADD => old_val.wrapping_add(self.oparg),
+            FutexWakeOpType::FUTEX_OP_OR => old_val | self.oparg,
+            FutexWakeOpType::FUTEX_OP_ANDN => old_val & !self.oparg,
+            FutexWakeOpType::FUTEX_OP_XOR => old_val ^ self.oparg,
+        };
+        new_val as i32
+    }
+
+    fn should_wake(&self, old_val: i32) -> bool {
+        match self.cmp {
+            FutexWakeOpCmp::FUTEX_OP_CMP_EQ => old_val == self.cmparg,
+            FutexWakeOpCmp::FUTEX_OP_CMP_NE => old_val != self.cmparg,
+            FutexWakeOpCmp::FUTEX_OP_CMP_LT => old_val < self.cmparg,
+            FutexWakeOpCmp::FUTEX_OP_CMP_LE => old_val <= self.cmparg,
+            FutexWakeOpCmp::FUTEX_OP_CMP_GT => old_val > self.cmparg,
+            FutexWakeOpCmp::FUTEX_OP_CMP_GE => old_val >= self.cmparg,
+        }
+    }
+}
+
 pub fn futex_op_and_flags_from_u32(bits: u32) -> Result<(FutexOp, FutexFlags)> {
     let op = {
         let op_bits = bits & FUTEX_OP_MASK;
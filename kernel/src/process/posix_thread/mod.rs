@@ -1,12 +1,15 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::sync::atomic::{AtomicU32, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, Ordering};
 
+use aster_block::ioprio::IoPriority;
 use aster_rights::{ReadOp, WriteOp};
 use ostd::sync::{RoArc, Waker};
 
 use super::{
     kill::SignalSenderIds,
+    ptrace::Tracee,
+    seccomp::Seccomp,
     signal::{
         sig_action::SigAction,
         sig_mask::{AtomicSigMask, SigMask, SigSet},
@@ -21,7 +24,7 @@ use crate::{
     events::Observer,
     fs::{file_table::FileTable, thread_info::ThreadFsInfo},
     prelude::*,
-    process::signal::constants::SIGCONT,
+    process::signal::constants::{SIGCONT, SIGKILL, SIGSTOP},
     thread::{Thread, Tid},
     time::{clocks::ProfClock, Timer, TimerManager},
 };
@@ -52,12 +55,22 @@ pub struct PosixThread {
 
     /// Process credentials. At the kernel level, credentials are a per-thread attribute.
     credentials: Credentials,
+    /// Whether the thread and its children cannot gain privileges via `execve`.
+    no_new_privs: AtomicBool,
+    /// The seccomp mode and filters.
+    seccomp: Seccomp,
+    /// The I/O priority, which is stored as the raw value of [`IoPriority`].
+    io_priority: AtomicU16,
+    /// The ptrace state.
+    tracee: Tracee,
+    /// Whether the thread is frozen by the cgroup freezer.
+    is_frozen: AtomicBool,
 
     // Files
     /// File table
     file_table: Mutex<Option<RoArc<FileTable>>>,
-    /// File system
-    fs: Arc<ThreadFsInfo>,
+    /// File system, which is shared with other threads if they are cloned with `CLONE_FS`
+    fs: RwMutex<Arc<ThreadFsInfo>>,
 
     // Signal
     /// Blocked signals
@@ -66,7 +79,7 @@ pub struct PosixThread {
     sig_queues: SigQueues,
     /// The per-thread signal [`Waker`], which will be used to wake up the thread
     /// when enqueuing a signal.
-    signalled_waker: SpinLock<Option<Arc<Waker>>>,
+    signalled_waker: SpinLock<Option<SignalledWaker>>,
 
     /// A profiling clock measures the user CPU time and kernel CPU time in the thread.
     prof_clock: Arc<ProfClock>,
@@ -78,6 +91,13 @@ pub struct PosixThread {
     prof_timer_manager: Arc<TimerManager>,
 }
 
+/// A [`Waker`] that is woken up when signals are enqueued.
+struct SignalledWaker {
+    waker: Arc<Waker>,
+    /// Whether the waker is woken up only when the thread is killed.
+    is_killable_only: bool,
+}
+
 impl PosixThread {
     pub fn process(&self) -> Arc<Process> {
         self.process.upgrade().unwrap()
@@ -100,10 +120,52 @@ impl PosixThread {
         &self.file_table
     }
 
-    pub fn fs(&self) -> &Arc<ThreadFsInfo> {
+    /// Returns the FS information of the thread.
+    ///
+    /// The FS information is replaced only by the thread itself when it unshares the FS
+    /// information with `unshare(CLONE_FS)`.
+    pub fn fs(&self) -> &RwMutex<Arc<ThreadFsInfo>> {
         &self.fs
     }
 
+    /// Returns whether the thread cannot gain privileges via `execve`.
+    ///
+    /// If this is true, the set-user-ID and set-group-ID bits of the executables are ignored.
+    pub fn no_new_privs(&self) -> bool {
+        self.no_new_privs.load(Ordering::Relaxed)
+    }
+
+    /// Forbids the thread from gaining privileges via `execve`.
+    ///
+    /// This cannot be undone.
+    pub fn set_no_new_privs(&self) {
+        self.no_new_privs.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns the seccomp state of the thread.
+    pub fn seccomp(&self) -> &Seccomp {
+        &self.seccomp
+    }
+
+    /// Returns the I/O priority of the thread.
+    ///
+    /// If no class is set, the I/O priority of the bios will be derived from the scheduling
+    /// policy of the thread.
+    pub fn io_priority(&self) -> IoPriority {
+        IoPriority::from_raw(self.io_priority.load(Ordering::Relaxed)).unwrap()
+    }
+
+    /// Sets the I/O priority of the thread.
+    pub fn set_io_priority(&self, io_priority: IoPriority) {
+        self.io_priority
+            .store(io_priority.to_raw(), Ordering::Relaxed);
+    }
+
+    /// Returns the ptrace state of the thread.
+    pub fn tracee(&self) -> &Tracee {
+        &self.tracee
+    }
+
     /// Get the reference to the signal mask of the thread.
     ///
     /// Note that while this function offers mutable access to the signal mask,
@@ -121,19 +183,35 @@ impl PosixThread {
     /// that are not blocked.
     pub fn has_pending(&self) -> bool {
         let blocked = self.sig_mask().load(Ordering::Relaxed);
+        // An interruption by the tracer or the cgroup freezer is handled like a signal.
         self.sig_queues.has_pending(blocked)
+            || self.tracee.is_interrupted()
+            || self.is_killed()
+            || self.process().cgroup().is_freezing()
+    }
+
+    /// Returns whether the thread has been killed.
+    ///
+    /// A thread is killed if `SIGKILL` is pending, regardless of the signal mask. A killed thread
+    /// will exit as soon as it returns to the user space, so blocking operations should be
+    /// cancelled to let it do so promptly.
+    pub fn is_killed(&self) -> bool {
+        self.sig_queues.sig_pending().contains(SIGKILL)
     }
 
     /// Returns whether the signal is blocked by the thread.
     pub(in crate::process) fn has_signal_blocked(&self, signum: SigNum) -> bool {
-        // FIMXE: Some signals cannot be blocked, even set in sig_mask.
+        // `SIGKILL` and `SIGSTOP` cannot be blocked, even if they are set in the signal mask.
+        if signum == SIGKILL || signum == SIGSTOP {
+            return false;
+        }
         self.sig_mask.contains(signum, Ordering::Relaxed)
     }
 
     /// Checks whether the signal can be delivered to the thread.
     ///
     /// For a signal can be delivered to the thread, the sending thread must either
-    /// be privileged, or the real or effective user ID of the sending thread must equal
+    /// have the `CAP_KILL` capability, or the real or effective user ID of the sending thread must equal
     /// the real or saved set-user-ID of the target thread.
     ///
     /// For SIGCONT, the sending and receiving processes should belong to the same session.
@@ -142,7 +220,7 @@ impl PosixThread {
         signum: Option<&SigNum>,
         sender: &SignalSenderIds,
     ) -> Result<()> {
-        if sender.euid().is_root() {
+        if sender.has_kill_capability() {
             return Ok(());
         }
 
@@ -189,25 +267,87 @@ impl PosixThread {
     /// If setting a new waker before clearing the current thread's signalled waker
     /// this method will panic.
     pub fn set_signalled_waker(&self, waker: Arc<Waker>) {
+        self.set_signalled_waker_impl(waker, false);
+    }
+
+    /// Sets the input [`Waker`] as the killable waker of this thread.
+    ///
+    /// This is similar to [`Self::set_signalled_waker`], except that the waker is woken up only
+    /// when the thread is killed (see [`Self::is_killed`]), not by other signals.
+    ///
+    /// # Panics
+    ///
+    /// If setting a new waker before clearing the current thread's signalled waker
+    /// this method will panic.
+    pub fn set_killable_waker(&self, waker: Arc<Waker>) {
+        self.set_signalled_waker_impl(waker, true);
+    }
+
+    fn set_signalled_waker_impl(&self, waker: Arc<Waker>, is_killable_only: bool) {
         let mut signalled_waker = self.signalled_waker.lock();
         assert!(signalled_waker.is_none());
-        *signalled_waker = Some(waker);
+        *signalled_waker = Some(SignalledWaker {
+            waker,
+            is_killable_only,
+        });
     }
 
-    /// Clears the signalled waker of this thread.
+    /// Clears the signalled waker (or the killable waker) of this thread.
     pub fn clear_signalled_waker(&self) {
         *self.signalled_waker.lock() = None;
     }
 
+    /// Returns whether the thread is in a wait that can be interrupted by signals.
+    ///
+    /// The signal-aware wait methods set the signalled waker while waiting, so such waits are
+    /// interruptible, while all other waits (including the killable ones) are not.
+    pub fn is_interruptible(&self) -> bool {
+        self.signalled_waker
+            .lock()
+            .as_ref()
+            .is_some_and(|signalled_waker| !signalled_waker.is_killable_only)
+    }
+
+    /// Returns whether the thread is in a wait that can be interrupted by signals, or at least by
+    /// the fatal signals.
+    pub fn is_killable(&self) -> bool {
+        self.signalled_waker.lock().is_some()
+    }
+
+    /// Wakes up the thread if it is in a wait that can be interrupted by signals.
+    pub(in crate::process) fn wake_up_interruptible(&self) {
+        if let Some(signalled_waker) = &*self.signalled_waker.lock()
+            && !signalled_waker.is_killable_only
+        {
+            signalled_waker.waker.wake_up();
+        }
+    }
+
+    /// Returns whether the thread is frozen by the cgroup freezer.
+    pub fn is_frozen(&self) -> bool {
+        self.is_frozen.load(Ordering::Relaxed)
+    }
+
+    pub(in crate::process) fn set_frozen(&self, is_frozen: bool) {
+        self.is_frozen.store(is_frozen, Ordering::Relaxed);
+    }
+
     /// Enqueues a thread-directed signal. This method should only be used for enqueue kernel
     /// signal and fault signal.
     pub fn enqueue_signal(&self, signal: Box<dyn Signal>) {
         let signal_number = signal.num();
         self.sig_queues.enqueue(signal);
-        if self.process().sig_dispositions().lock().get(signal_number) != SigAction::Ign
-            && let Some(waker) = &*self.signalled_waker.lock()
+        if signal_number == SIGKILL {
+            self.tracee.wake_up();
+            super::cgroup::wake_up_frozen();
+        }
+
+        let is_ignored =
+            self.process().sig_dispositions().lock().get(signal_number) == SigAction::Ign;
+        if let Some(signalled_waker) = &*self.signalled_waker.lock()
+            && (signal_number == SIGKILL || (!signalled_waker.is_killable_only && !is_ignored))
         {
-            waker.wake_up();
+            signalled_waker.waker.wake_up();
         }
     }
 
@@ -4,7 +4,12 @@
 
 use ostd::task::Task;
 
-use crate::{current_userspace, prelude::*, process::posix_thread::futex::futex_wake, thread::Tid};
+use crate::{
+    current_userspace,
+    prelude::*,
+    process::posix_thread::futex::{futex_wake, FUTEX_OWNER_DIED, FUTEX_TID_MASK, FUTEX_WAITERS},
+    thread::Tid,
+};
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod)]
@@ -32,18 +37,22 @@ pub struct RobustListHead {
 impl RobustListHead {
     /// Return an iterator for all futexes in the robust list.
     ///
+    /// The `head_ptr` is the user space address of the robust list head, which
+    /// marks the end of the list.
+    ///
     /// The futex referred to by `list_op_pending`, if any, will be returned as
     /// the last item.
-    pub fn futexes(&self) -> FutexIter<'_> {
-        FutexIter::new(self)
+    pub fn futexes(&self, head_ptr: Vaddr) -> FutexIter<'_> {
+        FutexIter::new(self, head_ptr)
     }
 
     /// Return the pending futex address if exist
     fn pending_futex_addr(&self) -> Option<Vaddr> {
-        if self.list_op_pending == 0 {
+        let pending_ptr = entry_ptr(self.list_op_pending);
+        if pending_ptr == 0 {
             None
         } else {
-            self.futex_addr(self.list_op_pending)
+            self.futex_addr(pending_ptr)
         }
     }
 
@@ -55,17 +64,27 @@ impl RobustListHead {
     }
 }
 
+/// Returns the address of a lock entry.
+///
+/// The lowest bit of the pointers in the robust list indicates whether the
+/// futex is a PI futex, so it should be cleared to get the real address.
+fn entry_ptr(ptr: Vaddr) -> Vaddr {
+    ptr & !1
+}
+
 pub struct FutexIter<'a> {
     robust_list: &'a RobustListHead,
+    head_ptr: Vaddr,
     entry_ptr: Vaddr,
     count: isize,
 }
 
 impl<'a> FutexIter<'a> {
-    pub fn new(robust_list: &'a RobustListHead) -> Self {
+    pub fn new(robust_list: &'a RobustListHead, head_ptr: Vaddr) -> Self {
         Self {
             robust_list,
-            entry_ptr: robust_list.list.next,
+            head_ptr,
+            entry_ptr: entry_ptr(robust_list.list.next),
             count: 0,
         }
     }
@@ -92,15 +111,14 @@ impl Iterator for FutexIter<'_> {
             return None;
         }
 
-        let end_ptr = self.robust_list.list.next;
-        while self.entry_ptr != end_ptr || self.count == 0 {
+        while self.entry_ptr != self.head_ptr {
             if self.count == ROBUST_LIST_LIMIT {
                 break;
             }
             if self.entry_ptr == 0 {
                 return None;
             }
-            let futex_addr = if self.entry_ptr != self.robust_list.list_op_pending {
+            let futex_addr = if self.entry_ptr != entry_ptr(self.robust_list.list_op_pending) {
                 self.robust_list.futex_addr(self.entry_ptr)
             } else {
                 None
@@ -109,7 +127,7 @@ impl Iterator for FutexIter<'_> {
             else {
                 return None;
             };
-            self.entry_ptr = robust_list.next;
+            self.entry_ptr = entry_ptr(robust_list.next);
             self.count += 1;
             if futex_addr.is_some() {
                 return futex_addr;
@@ -120,42 +138,41 @@ impl Iterator for FutexIter<'_> {
     }
 }
 
-const FUTEX_WAITERS: u32 = 0x8000_0000;
-const FUTEX_OWNER_DIED: u32 = 0x4000_0000;
-const FUTEX_TID_MASK: u32 = 0x3FFF_FFFF;
-
 /// Wakeup one robust futex owned by the thread
-/// FIXME: requires atomic operations here
 pub fn wake_robust_futex(futex_addr: Vaddr, tid: Tid) -> Result<()> {
     let task = Task::current().unwrap();
     let user_space = CurrentUserSpace::new(&task);
 
-    let futex_val = {
+    let mut futex_val = {
         if futex_addr == 0 {
             return_errno_with_message!(Errno::EINVAL, "invalid futext addr");
         }
         user_space.read_val::<u32>(futex_addr)?
     };
-    let mut old_val = futex_val;
+
     loop {
         // This futex may held by another thread, do nothing
-        if old_val & FUTEX_TID_MASK != tid {
-            break;
+        if futex_val & FUTEX_TID_MASK != tid {
+            return Ok(());
         }
-        let new_val = (old_val & FUTEX_WAITERS) | FUTEX_OWNER_DIED;
-        let cur_val = user_space.read_val(futex_addr)?;
-        if cur_val != new_val {
-            // The futex value has changed, let's retry with current value
-            old_val = cur_val;
-            user_space.write_val(futex_addr, &new_val)?;
-            continue;
-        }
-        // Wakeup one waiter
-        if cur_val & FUTEX_WAITERS != 0 {
-            debug!("wake robust futex addr: {:?}", futex_addr);
-            futex_wake(futex_addr, 1, None)?;
+
+        let new_val = (futex_val & FUTEX_WAITERS) | FUTEX_OWNER_DIED;
+        let prev_val = user_space.atomic_compare_exchange(futex_addr, futex_val, new_val)?;
+        if prev_val == futex_val {
+            break;
         }
-        break;
+        // The user space has changed the value concurrently, retry.
+        futex_val = prev_val;
     }
+
+    // Wakeup one waiter
+    //
+    // Robust futexes are always shared, as glibc does not use private futex
+    // operations for them.
+    if futex_val & FUTEX_WAITERS != 0 {
+        debug!("wake robust futex addr: {:?}", futex_addr);
+        futex_wake(futex_addr, 1, None)?;
+    }
+
     Ok(())
 }
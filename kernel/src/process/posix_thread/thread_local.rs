@@ -5,8 +5,15 @@ use core::cell::{Cell, Ref, RefCell, RefMut};
 use aster_rights::Full;
 use ostd::{mm::Vaddr, sync::RwArc, task::CurrentTask};
 
-use super::RobustListHead;
-use crate::{fs::file_table::FileTable, process::signal::SigStack, vm::vmar::Vmar};
+use crate::{
+    fs::{
+        file_table::{FileLookup, FileTable},
+        utils::ReadaheadHint,
+    },
+    prelude::*,
+    process::signal::SigStack,
+    vm::vmar::Vmar,
+};
 
 /// Local data for a POSIX thread.
 pub struct ThreadLocal {
@@ -20,10 +27,13 @@ pub struct ThreadLocal {
 
     // Robust futexes.
     // https://man7.org/linux/man-pages/man2/get_robust_list.2.html
-    robust_list: RefCell<Option<RobustListHead>>,
+    // The user space address of the robust list head.
+    robust_list: RefCell<Option<Vaddr>>,
 
     // Files.
     file_table: RefCell<Option<RwArc<FileTable>>>,
+    /// The lock-free view of the files in `file_table`.
+    file_lookup: RefCell<Arc<FileLookup>>,
 
     // Signal.
     /// `ucontext` address for the signal handler.
@@ -32,6 +42,17 @@ pub struct ThreadLocal {
     sig_context: Cell<Option<Vaddr>>,
     /// Stack address, size, and flags for the signal handler.
     sig_stack: RefCell<Option<SigStack>>,
+
+    // I/O.
+    /// Whether the ongoing I/O operation should fail with `EAGAIN` instead of blocking.
+    ///
+    /// This is set during the system calls with the `RWF_NOWAIT` flag and during the reads of
+    /// some devices opened with `O_NONBLOCK`.
+    is_io_nowait: Cell<bool>,
+    /// The readahead hint of the ongoing read operation.
+    ///
+    /// This is set during the reads of the files advised by `fadvise`.
+    readahead_hint: Cell<ReadaheadHint>,
 }
 
 impl ThreadLocal {
@@ -40,15 +61,21 @@ impl ThreadLocal {
         clear_child_tid: Vaddr,
         root_vmar: Vmar<Full>,
         file_table: RwArc<FileTable>,
+        sig_stack: Option<SigStack>,
     ) -> Self {
+        let file_lookup = file_table.read().lookup().clone();
+
         Self {
             set_child_tid: Cell::new(set_child_tid),
             clear_child_tid: Cell::new(clear_child_tid),
             root_vmar: RefCell::new(Some(root_vmar)),
             robust_list: RefCell::new(None),
             file_table: RefCell::new(Some(file_table)),
+            file_lookup: RefCell::new(file_lookup),
             sig_context: Cell::new(None),
-            sig_stack: RefCell::new(None),
+            sig_stack: RefCell::new(sig_stack),
+            is_io_nowait: Cell::new(false),
+            readahead_hint: Cell::new(ReadaheadHint::Normal),
         }
     }
 
@@ -64,7 +91,7 @@ impl ThreadLocal {
         &self.root_vmar
     }
 
-    pub fn robust_list(&self) -> &RefCell<Option<RobustListHead>> {
+    pub fn robust_list(&self) -> &RefCell<Option<Vaddr>> {
         &self.robust_list
     }
 
@@ -73,7 +100,7 @@ impl ThreadLocal {
     }
 
     pub fn borrow_file_table_mut(&self) -> FileTableRefMut {
-        FileTableRefMut(self.file_table.borrow_mut())
+        FileTableRefMut(self.file_table.borrow_mut(), self.file_lookup.borrow_mut())
     }
 
     pub fn sig_context(&self) -> &Cell<Option<Vaddr>> {
@@ -83,6 +110,14 @@ impl ThreadLocal {
     pub fn sig_stack(&self) -> &RefCell<Option<SigStack>> {
         &self.sig_stack
     }
+
+    pub fn is_io_nowait(&self) -> &Cell<bool> {
+        &self.is_io_nowait
+    }
+
+    pub fn readahead_hint(&self) -> &Cell<ReadaheadHint> {
+        &self.readahead_hint
+    }
 }
 
 /// An immutable, shared reference to the file table in [`ThreadLocal`].
@@ -100,7 +135,10 @@ impl FileTableRef<'_> {
 }
 
 /// A mutable, exclusive reference to the file table in [`ThreadLocal`].
-pub struct FileTableRefMut<'a>(RefMut<'a, Option<RwArc<FileTable>>>);
+pub struct FileTableRefMut<'a>(
+    RefMut<'a, Option<RwArc<FileTable>>>,
+    RefMut<'a, Arc<FileLookup>>,
+);
 
 impl FileTableRefMut<'_> {
     /// Unwraps and returns a reference to the file table.
@@ -112,6 +150,26 @@ impl FileTableRefMut<'_> {
         self.0.as_mut().unwrap()
     }
 
+    /// Unwraps and returns a reference to the file table, along with the lock-free view of the
+    /// files in it.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the thread has exited and the file table has been dropped.
+    pub fn unwrap_with_lookup(&mut self) -> (&mut RwArc<FileTable>, &FileLookup) {
+        (self.0.as_mut().unwrap(), &self.1)
+    }
+
+    /// Replaces the file table with a new one and returns the old one.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the thread has exited and the file table has been dropped.
+    pub fn replace(&mut self, file_table: RwArc<FileTable>) -> RwArc<FileTable> {
+        *self.1 = file_table.read().lookup().clone();
+        self.0.replace(file_table).unwrap()
+    }
+
     /// Removes the file table and drops it.
     pub(super) fn remove(&mut self) {
         *self.0 = None;
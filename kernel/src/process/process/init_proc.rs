@@ -10,8 +10,10 @@ use crate::{
         fs_resolver::{FsPath, AT_FDCWD},
         thread_info::ThreadFsInfo,
     },
+    net::namespace::NetNamespace,
     prelude::*,
     process::{
+        cgroup::Cgroup,
         posix_thread::{allocate_posix_tid, PosixThreadBuilder, ThreadName},
         process_table,
         process_vm::ProcessVm,
@@ -21,6 +23,7 @@ use crate::{
     },
     sched::Nice,
     thread::Tid,
+    time::namespace::TimeNamespace,
 };
 
 /// Creates and schedules the init process to run.
@@ -64,6 +67,9 @@ fn create_init_process(
         resource_limits,
         nice,
         sig_dispositions,
+        Cgroup::root().clone(),
+        TimeNamespace::get_init().clone(),
+        NetNamespace::get_init_singleton().clone(),
     );
 
     let init_task = create_init_task(
@@ -113,8 +119,8 @@ fn create_init_task(
         let elf_file = fs.resolver().read().lookup(&fs_path)?;
         let program_to_load =
             ProgramToLoad::build_from_file(elf_file, &fs_resolver, argv, envp, 1)?;
-        process_vm.clear_and_map();
-        program_to_load.load_to_vm(process_vm, &fs_resolver)?
+        process_vm.clear();
+        program_to_load.load_to_vm(process_vm, &fs_resolver, &credentials.dup().restrict())?
     };
 
     let mut user_ctx = UserContext::default();
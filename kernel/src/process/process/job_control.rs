@@ -1,9 +1,20 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use ostd::sync::{LocalIrqDisabled, WaitQueue};
+use core::sync::atomic::Ordering;
+
+use ostd::sync::LocalIrqDisabled;
 
 use super::{ProcessGroup, Session};
-use crate::prelude::*;
+use crate::{
+    prelude::*,
+    process::{
+        posix_thread::AsPosixThread,
+        signal::{
+            constants::SIGTTOU, sig_action::SigAction, sig_num::SigNum,
+            signals::kernel::KernelSignal,
+        },
+    },
+};
 
 /// The job control for terminals like TTY and PTY.
 ///
@@ -13,7 +24,6 @@ use crate::prelude::*;
 /// for a terminal.
 pub struct JobControl {
     inner: SpinLock<Inner, LocalIrqDisabled>,
-    wait_queue: WaitQueue,
 }
 
 #[derive(Default)]
@@ -27,7 +37,6 @@ impl JobControl {
     pub fn new() -> Self {
         Self {
             inner: SpinLock::new(Inner::default()),
-            wait_queue: WaitQueue::new(),
         }
     }
 
@@ -111,24 +120,32 @@ impl JobControl {
             &inner.session.upgrade().unwrap()
         ));
         inner.foreground = Arc::downgrade(process_group);
-
-        self.wait_queue.wake_all();
     }
 
-    /// Waits until the current process is in the foreground process group.
+    /// Checks whether the current process can access the terminal for job control.
     ///
-    /// Note that we only wait if the terminal is the our controlling terminal. If it isn't, the
-    /// method returns immediately without an error. This should match the Linux behavior where the
-    /// SIGTTIN won't be sent if we're reading the terminal that is the controlling terminal of
-    /// another session.
+    /// If the terminal is the controlling terminal of the current process and the current process
+    /// is not in the foreground process group, the access is from a background job. In this case,
+    /// `signal` (i.e., `SIGTTIN` for reads, or `SIGTTOU` for writes and changes of the terminal
+    /// settings) is sent to the process group of the current process, which will stop the job
+    /// until it is resumed in the foreground. Then the system call is restarted.
+    ///
+    /// The access is always allowed if the terminal is the controlling terminal of another
+    /// session. This should match the Linux behavior where the signal won't be sent in this case.
+    ///
+    /// # Errors
+    ///
+    /// This method fails with `ERESTARTSYS` if the signal has been sent. It fails with `EIO` if
+    /// the process group is orphaned, or if `SIGTTIN` is ignored or blocked. Note that if
+    /// `SIGTTOU` is ignored or blocked, the access is allowed instead.
     ///
     /// # Panics
     ///
     /// This method will panic if it is not called in the process context.
-    pub fn wait_until_in_foreground(&self) -> Result<()> {
+    pub fn check_background_access(&self, signal: SigNum) -> Result<()> {
         let current = current!();
 
-        self.wait_queue.pause_until(|| {
+        let process_group = {
             let process_group_mut = current.process_group.lock();
             let process_group = process_group_mut.upgrade().unwrap();
             let session = process_group.session().unwrap();
@@ -139,16 +156,44 @@ impl JobControl {
                 .upgrade()
                 .is_some_and(|terminal_session| Arc::ptr_eq(&terminal_session, &session))
             {
-                // The terminal is not our controlling terminal. Don't wait.
-                return Some(());
+                // The terminal is not our controlling terminal.
+                return Ok(());
             }
 
-            inner
-                .foreground
-                .upgrade()
-                .is_some_and(|terminal_foregroup| Arc::ptr_eq(&terminal_foregroup, &process_group))
-                .then_some(())
-        })
+            match inner.foreground.upgrade() {
+                Some(foreground) if !Arc::ptr_eq(&foreground, &process_group) => process_group,
+                // We're in the foreground, or there is no foreground process group.
+                _ => return Ok(()),
+            }
+        };
+
+        let is_signal_ignored = {
+            let posix_thread = current_thread!().as_posix_thread().unwrap();
+            posix_thread.sig_mask().contains(signal, Ordering::Relaxed)
+                || current.sig_dispositions().lock().get(signal) == SigAction::Ign
+        };
+        if is_signal_ignored {
+            if signal == SIGTTOU {
+                return Ok(());
+            }
+            return_errno_with_message!(
+                Errno::EIO,
+                "the background job cannot read from the terminal"
+            );
+        }
+
+        if process_group.is_orphaned() {
+            return_errno_with_message!(
+                Errno::EIO,
+                "the orphaned background job cannot access the terminal"
+            );
+        }
+
+        process_group.broadcast_signal(KernelSignal::new(signal));
+        return_errno_with_message!(
+            Errno::ERESTARTSYS,
+            "the background job is stopped for accessing the terminal"
+        );
     }
 }
 
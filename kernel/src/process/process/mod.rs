@@ -4,6 +4,8 @@ use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 
 use self::timer_manager::PosixTimerManager;
 use super::{
+    cgroup::Cgroup,
+    io_stats::IoStats,
     posix_thread::AsPosixThread,
     process_table,
     process_vm::{Heap, InitStackReader, ProcessVm, ProcessVmarGuard},
@@ -17,10 +19,11 @@ use super::{
     task_set::TaskSet,
 };
 use crate::{
+    net::namespace::NetNamespace,
     prelude::*,
     sched::{AtomicNice, Nice},
-    thread::{AsThread, Thread},
-    time::clocks::ProfClock,
+    thread::{AsThread, Thread, Tid},
+    time::{clocks::ProfClock, namespace::TimeNamespace},
 };
 
 mod init_proc;
@@ -75,6 +78,8 @@ pub struct Process {
     pub(super) parent: ParentProcess,
     /// Children processes
     children: Mutex<BTreeMap<Pid, Arc<Process>>>,
+    /// The threads traced by the process
+    tracees: Mutex<BTreeMap<Tid, Arc<Thread>>>,
     /// Process group
     pub(super) process_group: Mutex<Weak<ProcessGroup>>,
     /// resource limits
@@ -113,6 +118,22 @@ pub struct Process {
 
     /// A manager that manages timer resources and utilities of the process.
     timer_manager: PosixTimerManager,
+
+    /// The cgroup that the process belongs to.
+    cgroup: RwLock<Arc<Cgroup>>,
+
+    /// The time namespace that the process belongs to.
+    time_ns: Arc<TimeNamespace>,
+    /// The time namespace that the children of the process will belong to.
+    ///
+    /// This differs from `time_ns` after `unshare(CLONE_NEWTIME)`.
+    time_ns_for_children: Mutex<Arc<TimeNamespace>>,
+
+    /// The network namespace that the process belongs to.
+    net_ns: Mutex<Arc<NetNamespace>>,
+
+    /// The I/O statistics of all the threads in the process.
+    io_stats: IoStats,
 }
 
 /// Representing a parent process by holding a weak reference to it and its PID.
@@ -191,6 +212,9 @@ impl Process {
         resource_limits: ResourceLimits,
         nice: Nice,
         sig_dispositions: Arc<Mutex<SigDispositions>>,
+        cgroup: Arc<Cgroup>,
+        time_ns: Arc<TimeNamespace>,
+        net_ns: Arc<NetNamespace>,
     ) -> Arc<Self> {
         // SIGCHID does not interrupt pauser. Child process will
         // resume paused parent when doing exit.
@@ -198,6 +222,8 @@ impl Process {
 
         let prof_clock = ProfClock::new();
 
+        time_ns.mark_entered();
+
         Arc::new_cyclic(|process_ref: &Weak<Process>| Self {
             pid,
             tasks: Mutex::new(TaskSet::new()),
@@ -207,6 +233,7 @@ impl Process {
             status: ProcessStatus::default(),
             parent: ParentProcess::new(parent),
             children: Mutex::new(BTreeMap::new()),
+            tracees: Mutex::new(BTreeMap::new()),
             process_group: Mutex::new(Weak::new()),
             is_child_subreaper: AtomicBool::new(false),
             has_child_subreaper: AtomicBool::new(false),
@@ -217,6 +244,11 @@ impl Process {
             nice: AtomicNice::new(nice),
             timer_manager: PosixTimerManager::new(&prof_clock, process_ref),
             prof_clock,
+            cgroup: RwLock::new(cgroup),
+            time_ns_for_children: Mutex::new(time_ns.clone()),
+            time_ns,
+            net_ns: Mutex::new(net_ns),
+            io_stats: IoStats::new(),
         })
     }
 
@@ -244,6 +276,11 @@ impl Process {
         &self.prof_clock
     }
 
+    /// Gets the I/O statistics of the process.
+    pub fn io_stats(&self) -> &IoStats {
+        &self.io_stats
+    }
+
     /// Gets the timer resources and utilities of the process.
     pub fn timer_manager(&self) -> &PosixTimerManager {
         &self.timer_manager
@@ -269,6 +306,45 @@ impl Process {
         &self.nice
     }
 
+    /// Returns the cgroup that the process belongs to.
+    pub fn cgroup(&self) -> Arc<Cgroup> {
+        self.cgroup.read().clone()
+    }
+
+    /// Sets the cgroup of the process.
+    ///
+    /// This method should only be called by [`Cgroup::attach`], which also updates the threads.
+    pub(in crate::process) fn set_cgroup(&self, cgroup: Arc<Cgroup>) {
+        *self.cgroup.write() = cgroup;
+    }
+
+    /// Returns the time namespace that the process belongs to.
+    pub fn time_ns(&self) -> &Arc<TimeNamespace> {
+        &self.time_ns
+    }
+
+    /// Returns the time namespace that the children of the process will belong to.
+    pub fn time_ns_for_children(&self) -> Arc<TimeNamespace> {
+        self.time_ns_for_children.lock().clone()
+    }
+
+    /// Sets the time namespace that the children of the process will belong to.
+    pub fn set_time_ns_for_children(&self, time_ns: Arc<TimeNamespace>) {
+        *self.time_ns_for_children.lock() = time_ns;
+    }
+
+    /// Returns the network namespace that the process belongs to.
+    pub fn net_ns(&self) -> Arc<NetNamespace> {
+        self.net_ns.lock().clone()
+    }
+
+    /// Sets the network namespace of the process.
+    ///
+    /// The sockets that have been created stay in their original namespaces.
+    pub fn set_net_ns(&self, net_ns: Arc<NetNamespace>) {
+        *self.net_ns.lock() = net_ns;
+    }
+
     pub fn main_thread(&self) -> Arc<Thread> {
         self.tasks.lock().main().as_thread().unwrap().clone()
     }
@@ -287,6 +363,10 @@ impl Process {
         &self.children
     }
 
+    pub(super) fn tracees(&self) -> &Mutex<BTreeMap<Tid, Arc<Thread>>> {
+        &self.tracees
+    }
+
     pub fn children_wait_queue(&self) -> &WaitQueue {
         &self.children_wait_queue
     }
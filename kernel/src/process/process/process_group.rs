@@ -3,7 +3,7 @@
 use alloc::collections::btree_map::Values;
 
 use super::{Pgid, Pid, Process, Session};
-use crate::{prelude::*, process::signal::signals::Signal};
+use crate::{prelude::*, process::signal::signals::Signal, thread::AsThread};
 
 /// A process group.
 ///
@@ -59,6 +59,43 @@ impl ProcessGroup {
         }
     }
 
+    /// Returns whether the process group is orphaned.
+    ///
+    /// A process group is orphaned if the parent of every member is either in the same process
+    /// group or in a different session. Such a process group is not controlled by a job-control
+    /// shell, so its members should not be stopped by job-control signals.
+    pub(in crate::process) fn is_orphaned(&self) -> bool {
+        let processes: Vec<_> = self.inner.lock().processes.values().cloned().collect();
+        let parents = processes
+            .iter()
+            .filter(|process| !process.status().is_zombie())
+            .filter_map(|process| process.parent().lock().process().upgrade());
+
+        let session = self.session.upgrade();
+        !parents.into_iter().any(|parent| {
+            let Some(parent_group) = parent.process_group.lock().upgrade() else {
+                return false;
+            };
+            parent_group.pgid() != self.pgid
+                && parent_group
+                    .session()
+                    .zip(session.as_ref())
+                    .is_some_and(|(parent_session, session)| Arc::ptr_eq(&parent_session, session))
+        })
+    }
+
+    /// Returns whether any member of the process group is stopped.
+    pub(in crate::process) fn has_stopped_members(&self) -> bool {
+        self.inner.lock().processes.values().any(|process| {
+            process
+                .tasks()
+                .lock()
+                .as_slice()
+                .iter()
+                .any(|task| task.as_thread().is_some_and(|thread| thread.is_stopped()))
+        })
+    }
+
     /// Broadcasts the signal to all processes in the process group.
     ///
     /// This method should only be used to broadcast fault signals and kernel signals.
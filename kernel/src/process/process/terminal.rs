@@ -6,8 +6,11 @@ use super::{session::SessionGuard, JobControl, Pgid, Process, Session, Sid};
 use crate::{
     current_userspace,
     fs::{inode_handle::FileIo, utils::IoctlCmd},
-    prelude::{current, return_errno_with_message, warn, Errno, Error, Result},
-    process::process_table,
+    prelude::{current, current_thread, return_errno_with_message, warn, Errno, Error, Result},
+    process::{
+        credentials::capabilities::CapSet, posix_thread::AsPosixThread, process_table,
+        signal::constants::SIGTTOU,
+    },
 };
 
 /// A terminal.
@@ -17,6 +20,9 @@ use crate::{
 pub trait Terminal: FileIo {
     /// Returns the job control of the terminal.
     fn job_control(&self) -> &JobControl;
+
+    /// Pushes the byte to the input of the terminal as if it were typed.
+    fn push_input(&self, byte: u8);
 }
 
 impl dyn Terminal {
@@ -29,6 +35,7 @@ impl dyn Terminal {
                     return_errno_with_message!(Errno::EINVAL, "negative PGIDs are not valid");
                 }
 
+                self.job_control().check_background_access(SIGTTOU)?;
                 self.set_foreground(pgid, &current!())
             }
             IoctlCmd::TIOCGPGRP => {
@@ -83,6 +90,27 @@ impl dyn Terminal {
                 current_userspace!().write_val::<Sid>(arg, &sid)
             }
 
+            // Commands about the input
+            IoctlCmd::TIOCSTI => {
+                // Injecting input to a terminal other than the controlling terminal requires the
+                // privilege, because it allows to run commands on behalf of another user.
+                let is_privileged = current_thread!()
+                    .as_posix_thread()
+                    .unwrap()
+                    .credentials()
+                    .has_capability(CapSet::SYS_ADMIN);
+                if !is_privileged && self.is_control_and(&current!(), |_, _| Ok(())).is_err() {
+                    return_errno_with_message!(
+                        Errno::EPERM,
+                        "the terminal is not our controlling terminal"
+                    );
+                }
+
+                let byte = current_userspace!().read_val::<u8>(arg)?;
+                self.push_input(byte);
+                Ok(())
+            }
+
             // Commands that are invalid or not supported
             _ => {
                 return_errno_with_message!(Errno::EINVAL, "the `ioctl` command is invalid")
@@ -124,7 +152,7 @@ impl dyn Terminal {
     }
 
     /// Unsets the terminal from the controlling terminal of the process.
-    fn unset_control(self: Arc<Self>, process: &Process) -> Result<()> {
+    pub(in crate::process) fn unset_control(self: Arc<Self>, process: &Process) -> Result<()> {
         // Lock order: group of process -> session inner -> job control
         self.is_control_and(process, |session, session_inner| {
             if !session.is_leader(process) {
@@ -16,6 +16,7 @@ use ostd::{
 
 use super::Process;
 use crate::{
+    prelude::*,
     process::{
         posix_thread::AsPosixThread,
         signal::{constants::SIGALRM, signals::kernel::KernelSignal},
@@ -174,17 +175,19 @@ impl PosixTimerManager {
 
     /// Adds a POSIX timer to the managed `posix_timers`, and allocate a timer ID for this timer.
     /// Return the timer ID.
-    pub fn add_posix_timer(&self, posix_timer: Arc<Timer>) -> usize {
+    pub fn add_posix_timer(&self, posix_timer: Arc<Timer>) -> Result<usize> {
         let mut timers = self.posix_timers.lock();
         // Holding the lock of `posix_timers` is required to operate the `id_allocator`.
-        let timer_id = self.id_allocator.lock().alloc().unwrap();
+        let Some(timer_id) = self.id_allocator.lock().alloc() else {
+            return_errno_with_message!(Errno::EAGAIN, "too many POSIX timers");
+        };
         if timers.len() < timer_id + 1 {
             timers.resize(timer_id + 1, None);
         }
         // The ID allocated is not used by any other timers so this index in `timers`
         // must be `None`.
         timers[timer_id] = Some(posix_timer);
-        timer_id
+        Ok(timer_id)
     }
 
     /// Finds a POSIX timer by the input `timer_id`.
@@ -211,4 +214,18 @@ impl PosixTimerManager {
         }
         timer
     }
+
+    /// Cancels and removes all the POSIX timers.
+    ///
+    /// This should be called when the process executes a new program or exits.
+    pub fn clear_posix_timers(&self) {
+        let mut timers = self.posix_timers.lock();
+        let mut id_allocator = self.id_allocator.lock();
+        for (timer_id, timer) in timers.drain(..).enumerate() {
+            if let Some(timer) = timer {
+                timer.cancel();
+                id_allocator.free(timer_id);
+            }
+        }
+    }
 }
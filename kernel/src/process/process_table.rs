@@ -28,6 +28,16 @@ pub fn process_table_mut() -> MutexGuard<'static, ProcessTable> {
     PROCESS_TABLE.lock()
 }
 
+/// Returns the number of the threads of all processes.
+pub fn nr_threads() -> usize {
+    // The tasks are not locked while the process table is locked.
+    let processes = PROCESS_TABLE.lock().iter().cloned().collect::<Vec<_>>();
+    processes
+        .iter()
+        .map(|process| process.tasks().lock().as_slice().len())
+        .sum()
+}
+
 /// Process Table.
 pub struct ProcessTable {
     inner: BTreeMap<Pid, Arc<Process>>,
@@ -1,117 +1,140 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::sync::atomic::{AtomicUsize, Ordering};
-
 use align_ext::AlignExt;
 use aster_rights::Full;
+use ostd_pod::Pod;
 
 use crate::{
     prelude::*,
+    process::ResourceType,
+    util::random::getrandom,
     vm::{perms::VmPerms, vmar::Vmar},
 };
 
-/// The base address of user heap
-pub const USER_HEAP_BASE: Vaddr = 0x0000_0000_1000_0000;
-/// The max allowed size of user heap
-pub const USER_HEAP_SIZE_LIMIT: usize = 16 * 1024 * PAGE_SIZE; // 16 * 4MB
-
+/// The base address of user heap before a program is loaded
+const USER_HEAP_BASE: Vaddr = 0x0000_0000_1000_0000;
+/// The size of the address range reserved for the growth of user heap
+const USER_HEAP_RESERVED_SIZE: usize = 16 * 1024 * PAGE_SIZE; // 16 * 4MB
+/// The maximum size of the random gap between the loaded program and the user heap,
+/// which is the same as `arch_randomize_brk` in Linux
+const USER_HEAP_RANDOM_RANGE: usize = 0x0200_0000; // 32MB
+
+/// The user heap, whose end is the program break.
+///
+/// The heap is mapped as a separate anonymous mapping from its base to the
+/// page-aligned program break. The mapping is absent if the heap is empty.
 #[derive(Debug)]
 pub struct Heap {
-    /// The lowest address of the heap
+    inner: Mutex<HeapInner>,
+}
+
+#[derive(Clone, Debug)]
+struct HeapInner {
+    /// The lowest address of the heap, i.e., the initial program break
     base: Vaddr,
-    /// The heap size limit
-    limit: usize,
-    /// The current heap highest address
-    current_heap_end: AtomicUsize,
+    /// The current program break, which is not necessarily page-aligned
+    brk: Vaddr,
 }
 
 impl Heap {
     pub const fn new() -> Self {
         Heap {
-            base: USER_HEAP_BASE,
-            limit: USER_HEAP_SIZE_LIMIT,
-            current_heap_end: AtomicUsize::new(USER_HEAP_BASE),
+            inner: Mutex::new(HeapInner {
+                base: USER_HEAP_BASE,
+                brk: USER_HEAP_BASE,
+            }),
         }
     }
 
-    /// Initializes and maps the heap virtual memory.
-    pub(super) fn alloc_and_map_vm(&self, root_vmar: &Vmar<Full>) -> Result<()> {
-        let vmar_map_options = {
-            let perms = VmPerms::READ | VmPerms::WRITE;
-            root_vmar
-                .new_map(PAGE_SIZE, perms)
-                .unwrap()
-                .offset(self.base)
-        };
-        vmar_map_options.build()?;
-
-        // If we touch another mapped range when we are trying to expand the
-        // heap, we fail.
-        //
-        // So a simple solution is to reserve enough space for the heap by
-        // mapping without any permissions and allow it to be overwritten
-        // later by `brk`. New mappings from `mmap` that overlaps this range
-        // may be moved to another place.
-        let vmar_reserve_options = {
-            let perms = VmPerms::empty();
-            root_vmar
-                .new_map(USER_HEAP_SIZE_LIMIT - PAGE_SIZE, perms)
-                .unwrap()
-                .offset(self.base + PAGE_SIZE)
-        };
-        vmar_reserve_options.build()?;
+    /// Initializes the heap after the program that ends at `program_end`.
+    ///
+    /// The heap starts at a random address above the program, and the
+    /// address range above the heap is reserved so that the new mappings from
+    /// `mmap` are placed elsewhere if possible.
+    pub(in crate::process) fn init(&self, root_vmar: &Vmar<Full>, program_end: Vaddr) {
+        let mut random_value = 0usize;
+        getrandom(random_value.as_bytes_mut()).unwrap();
+        let random_offset = (random_value % USER_HEAP_RANDOM_RANGE).align_down(PAGE_SIZE);
 
-        self.set_uninitialized();
-        Ok(())
+        let base = program_end.align_up(PAGE_SIZE) + random_offset;
+        root_vmar.set_reserved_region(Some(base..base + USER_HEAP_RESERVED_SIZE));
+
+        *self.inner.lock() = HeapInner { base, brk: base };
     }
 
+    /// Sets the program break to `new_heap_end`, and returns the new program
+    /// break.
+    ///
+    /// Like Linux, if the program break cannot be changed, the current
+    /// program break is returned instead of an error.
     pub fn brk(&self, new_heap_end: Option<Vaddr>, ctx: &Context) -> Result<Vaddr> {
-        let user_space = ctx.user_space();
-        let root_vmar = user_space.root_vmar();
-        match new_heap_end {
-            None => Ok(self.current_heap_end.load(Ordering::Relaxed)),
-            Some(new_heap_end) => {
-                if new_heap_end > self.base + self.limit {
-                    return_errno_with_message!(Errno::ENOMEM, "heap size limit was met.");
-                }
-                let current_heap_end = self.current_heap_end.load(Ordering::Acquire);
-
-                if new_heap_end <= current_heap_end {
-                    // FIXME: should we allow shrink current user heap?
-                    return Ok(current_heap_end);
-                }
-
-                let current_heap_end = current_heap_end.align_up(PAGE_SIZE);
-                let new_heap_end = new_heap_end.align_up(PAGE_SIZE);
-
-                // Remove the reserved space.
-                root_vmar.remove_mapping(current_heap_end..new_heap_end)?;
+        let mut inner = self.inner.lock();
+        let Some(new_heap_end) = new_heap_end else {
+            return Ok(inner.brk);
+        };
+        if new_heap_end < inner.base {
+            return Ok(inner.brk);
+        }
 
-                let old_size = current_heap_end - self.base;
-                let new_size = new_heap_end - self.base;
+        let data_limit = ctx
+            .process
+            .resource_limits()
+            .get_rlimit(ResourceType::RLIMIT_DATA)
+            .get_cur();
+        if (new_heap_end - inner.base) as u64 > data_limit {
+            return Ok(inner.brk);
+        }
 
-                // Expand the heap.
-                root_vmar.resize_mapping(self.base, old_size, new_size)?;
+        let Some(new_mapped_end) = new_heap_end.checked_next_multiple_of(PAGE_SIZE) else {
+            return Ok(inner.brk);
+        };
+        let old_mapped_end = inner.brk.align_up(PAGE_SIZE);
 
-                self.current_heap_end.store(new_heap_end, Ordering::Release);
-                Ok(new_heap_end)
+        let user_space = ctx.user_space();
+        let root_vmar = user_space.root_vmar();
+        if new_mapped_end < old_mapped_end {
+            root_vmar.remove_mapping(new_mapped_end..old_mapped_end)?;
+        } else if new_mapped_end > old_mapped_end {
+            // The new pages are merged into the heap mapping. The expansion
+            // fails if the pages are occupied by other mappings.
+            let expanded = root_vmar
+                .new_map(
+                    new_mapped_end - old_mapped_end,
+                    VmPerms::READ | VmPerms::WRITE,
+                )?
+                .offset(old_mapped_end)
+                .build();
+            if expanded.is_err() {
+                return Ok(inner.brk);
             }
         }
+
+        inner.brk = new_heap_end;
+        Ok(new_heap_end)
     }
 
-    pub(super) fn set_uninitialized(&self) {
-        self.current_heap_end
-            .store(self.base + PAGE_SIZE, Ordering::Relaxed);
+    /// Sets the base or the program break of the heap without changing any
+    /// mappings.
+    ///
+    /// This is used by checkpoint/restore tools, which restore the heap
+    /// mapping by themselves. The base must not be above the program break.
+    pub fn set_base_and_brk(&self, new_base: Option<Vaddr>, new_brk: Option<Vaddr>) -> Result<()> {
+        let mut inner = self.inner.lock();
+        let base = new_base.unwrap_or(inner.base);
+        let brk = new_brk.unwrap_or(inner.brk);
+        if base > brk {
+            return_errno_with_message!(Errno::EINVAL, "the heap base is above the program break");
+        }
+
+        *inner = HeapInner { base, brk };
+        Ok(())
     }
 }
 
 impl Clone for Heap {
     fn clone(&self) -> Self {
-        let current_heap_end = self.current_heap_end.load(Ordering::Relaxed);
         Self {
-            base: self.base,
-            limit: self.limit,
-            current_heap_end: AtomicUsize::new(current_heap_end),
+            inner: Mutex::new(self.inner.lock().clone()),
         }
     }
 }
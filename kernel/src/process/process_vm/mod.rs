@@ -12,24 +12,21 @@
 mod heap;
 mod init_stack;
 
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 use aster_rights::Full;
 pub use heap::Heap;
 use ostd::{sync::MutexGuard, task::disable_preempt};
 
-pub use self::{
-    heap::USER_HEAP_SIZE_LIMIT,
-    init_stack::{
-        aux_vec::{AuxKey, AuxVec},
-        InitStack, InitStackReader, INIT_STACK_SIZE, MAX_ARGV_NUMBER, MAX_ARG_LEN, MAX_ENVP_NUMBER,
-        MAX_ENV_LEN,
-    },
+pub use self::init_stack::{
+    aux_vec::{AuxKey, AuxVec},
+    InitStack, InitStackReader, INIT_STACK_SIZE, MAX_ARGV_NUMBER, MAX_ARG_LEN, MAX_ENVP_NUMBER,
+    MAX_ENV_LEN,
 };
-use crate::{prelude::*, vm::vmar::Vmar};
+use crate::{ipc::shm::collect_destroyed_shm, prelude::*, vm::vmar::Vmar};
 
 /*
  * The user's virtual memory space layout looks like below.
- * TODO: The layout of the userheap does not match the current implementation,
- * And currently the initial program break is a fixed value.
  *
  *  (high address)
  *  +---------------------+ <------+ The top of Vmar, which is the highest address usable
@@ -67,6 +64,8 @@ pub struct ProcessVm {
     root_vmar: Mutex<Option<Vmar<Full>>>,
     init_stack: InitStack,
     heap: Heap,
+    /// The peak number of the resident pages sampled when the VMAR is cleared or dropped.
+    max_resident_pages: AtomicUsize,
 }
 
 /// A guard to the [`Vmar`] used by a process.
@@ -75,6 +74,7 @@ pub struct ProcessVm {
 /// the [`ProcessVm::lock_root_vmar`] method.
 pub struct ProcessVmarGuard<'a> {
     inner: MutexGuard<'a, Option<Vmar<Full>>>,
+    max_resident_pages: &'a AtomicUsize,
 }
 
 impl ProcessVmarGuard<'_> {
@@ -87,13 +87,28 @@ impl ProcessVmarGuard<'_> {
         self.inner.as_ref().unwrap()
     }
 
+    /// Returns a reference to the process VMAR, or `None` if the process has exited and its VMAR
+    /// has been dropped.
+    pub fn get(&self) -> Option<&Vmar<Full>> {
+        self.inner.as_ref()
+    }
+
     /// Sets a new VMAR for the binding process.
     ///
     /// If the `new_vmar` is `None`, this method will remove the
     /// current VMAR.
     pub(super) fn set_vmar(&mut self, new_vmar: Option<Vmar<Full>>) {
+        self.sample_resident_pages();
         *self.inner = new_vmar;
     }
+
+    /// Updates the peak number of the resident pages with that of the current VMAR.
+    fn sample_resident_pages(&self) {
+        if let Some(vmar) = self.inner.as_ref() {
+            self.max_resident_pages
+                .fetch_max(vmar.resident_pages(), Ordering::Relaxed);
+        }
+    }
 }
 
 impl Clone for ProcessVm {
@@ -103,6 +118,7 @@ impl Clone for ProcessVm {
             root_vmar: Mutex::new(Some(root_vmar.unwrap().dup().unwrap())),
             init_stack: self.init_stack.clone(),
             heap: self.heap.clone(),
+            max_resident_pages: AtomicUsize::new(self.max_resident_pages.load(Ordering::Relaxed)),
         }
     }
 }
@@ -113,11 +129,11 @@ impl ProcessVm {
         let root_vmar = Vmar::<Full>::new_root();
         let init_stack = InitStack::new();
         let heap = Heap::new();
-        heap.alloc_and_map_vm(&root_vmar).unwrap();
         Self {
             root_vmar: Mutex::new(Some(root_vmar)),
             heap,
             init_stack,
+            max_resident_pages: AtomicUsize::new(0),
         }
     }
 
@@ -131,6 +147,7 @@ impl ProcessVm {
             root_vmar,
             heap: other.heap.clone(),
             init_stack: other.init_stack.clone(),
+            max_resident_pages: AtomicUsize::new(0),
         })
     }
 
@@ -138,9 +155,20 @@ impl ProcessVm {
     pub fn lock_root_vmar(&self) -> ProcessVmarGuard {
         ProcessVmarGuard {
             inner: self.root_vmar.lock(),
+            max_resident_pages: &self.max_resident_pages,
         }
     }
 
+    /// Returns the peak number of the resident pages, i.e., the peak resident set size in pages.
+    ///
+    /// The resident pages are counted when the mappings are cleared by `execve` or dropped on
+    /// exit, not on every page fault. So a peak that is followed by `munmap` may be missed.
+    pub fn max_resident_pages(&self) -> usize {
+        let root_vmar = self.lock_root_vmar();
+        let current = root_vmar.get().map_or(0, |vmar| vmar.resident_pages());
+        current.max(self.max_resident_pages.load(Ordering::Relaxed))
+    }
+
     /// Returns a reader for reading contents from
     /// the `InitStack`.
     pub fn init_stack_reader(&self) -> InitStackReader {
@@ -167,16 +195,20 @@ impl ProcessVm {
         &self.heap
     }
 
-    /// Clears existing mappings and then maps the heap VMO to the current VMAR.
-    pub fn clear_and_map(&self) {
+    /// Clears existing mappings of the current VMAR.
+    ///
+    /// The heap is initialized again when a new program is loaded.
+    pub fn clear(&self) {
         let root_vmar = self.lock_root_vmar();
+        root_vmar.sample_resident_pages();
         root_vmar.unwrap().clear().unwrap();
-        self.heap.alloc_and_map_vm(&root_vmar.unwrap()).unwrap();
     }
 }
 
-/// Renews the [`ProcessVm`] of the current process and then maps the heap VMO to the new VMAR.
-pub fn renew_vm_and_map(ctx: &Context) {
+/// Renews the [`ProcessVm`] of the current process with a new empty VMAR.
+///
+/// The heap is initialized again when a new program is loaded.
+pub fn renew_vm(ctx: &Context) {
     let process_vm = ctx.process.vm();
     let mut root_vmar = process_vm.lock_root_vmar();
 
@@ -187,8 +219,6 @@ pub fn renew_vm_and_map(ctx: &Context) {
     root_vmar.set_vmar(Some(new_vmar));
     drop(guard);
 
-    process_vm
-        .heap
-        .alloc_and_map_vm(root_vmar.unwrap())
-        .unwrap();
+    // The shared memory segments detached by dropping the old VMAR may need to be destroyed.
+    collect_destroyed_shm();
 }
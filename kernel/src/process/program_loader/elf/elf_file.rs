@@ -98,21 +98,22 @@ impl Elf {
             if type_ == program::Type::Interp {
                 let file_size = program_header.file_size as usize;
                 let file_offset = program_header.offset as usize;
-                debug_assert!(file_offset + file_size <= file_header_buf.len());
-                let ldso = CStr::from_bytes_with_nul(
-                    &file_header_buf[file_offset..file_offset + file_size],
-                )?;
+                // TODO: Read the interpreter path from the file if it is not in the first page.
+                let Some(ldso_buf) = file_offset
+                    .checked_add(file_size)
+                    .and_then(|file_end| file_header_buf.get(file_offset..file_end))
+                else {
+                    return_errno_with_message!(
+                        Errno::ENOEXEC,
+                        "the interpreter path is not in the file header"
+                    );
+                };
+                let ldso = CStr::from_bytes_with_nul(ldso_buf)?;
                 return Ok(Some(ldso.to_string_lossy().to_string()));
             }
         }
         Ok(None)
     }
-
-    // An offset to be subtracted from ELF vaddr for PIE
-    pub fn base_load_address_offset(&self) -> u64 {
-        let phdr = self.program_headers.first().unwrap();
-        phdr.virtual_addr - phdr.offset
-    }
 }
 
 pub struct ElfHeader {
@@ -6,9 +6,9 @@
 //! When create a process from elf file, we will use the elf_load_info to construct the VmSpace
 
 use align_ext::AlignExt;
-use aster_rights::Full;
+use aster_rights::{Full, ReadOp};
 use ostd::{
-    mm::{CachePolicy, PageFlags, PageProperty, VmIo},
+    mm::{CachePolicy, PageFlags, PageProperty, VmIo, MAX_USERSPACE_VADDR},
     task::disable_preempt,
 };
 use xmas_elf::program::{self, ProgramHeader64};
@@ -23,8 +23,9 @@ use crate::{
     process::{
         posix_thread::do_exit_group,
         process_vm::{AuxKey, AuxVec, ProcessVm},
-        TermStatus,
+        Credentials, TermStatus,
     },
+    util::random::getrandom,
     vdso::{vdso_vmo, VDSO_VMO_SIZE},
     vm::{
         perms::VmPerms,
@@ -38,6 +39,9 @@ use crate::{
 ///
 /// This function will map elf segments and
 /// initialize process init stack.
+///
+/// The `credentials` are the credentials that the process will have after the program is
+/// loaded. They are passed to the program via the auxiliary vector.
 pub fn load_elf_to_vm(
     process_vm: &ProcessVm,
     file_header: &[u8],
@@ -45,12 +49,13 @@ pub fn load_elf_to_vm(
     fs_resolver: &FsResolver,
     argv: Vec<CString>,
     envp: Vec<CString>,
+    credentials: &Credentials<ReadOp>,
 ) -> Result<ElfLoadInfo> {
     let parsed_elf = Elf::parse_elf(file_header)?;
 
     let ldso = lookup_and_parse_ldso(&parsed_elf, file_header, fs_resolver)?;
 
-    match init_and_map_vmos(process_vm, ldso, &parsed_elf, &elf_file) {
+    match init_and_map_vmos(process_vm, ldso, &parsed_elf, &elf_file, credentials) {
         Ok((entry_point, mut aux_vec)) => {
             // Map and set vdso entry.
             // Since vdso does not require being mapped to any specific address,
@@ -110,10 +115,10 @@ fn lookup_and_parse_ldso(
 }
 
 fn load_ldso(root_vmar: &Vmar<Full>, ldso_file: &Dentry, ldso_elf: &Elf) -> Result<LdsoLoadInfo> {
-    let map_addr = map_segment_vmos(ldso_elf, root_vmar, ldso_file)?;
+    let load_bias = map_segment_vmos(ldso_elf, root_vmar, ldso_file, None)?;
     Ok(LdsoLoadInfo::new(
-        ldso_elf.entry_point() + map_addr,
-        map_addr,
+        ldso_elf.entry_point() + load_bias,
+        load_bias,
     ))
 }
 
@@ -122,40 +127,71 @@ fn init_and_map_vmos(
     ldso: Option<(Dentry, Elf)>,
     parsed_elf: &Elf,
     elf_file: &Dentry,
+    credentials: &Credentials<ReadOp>,
 ) -> Result<(Vaddr, AuxVec)> {
     let process_vmar = process_vm.lock_root_vmar();
     let root_vmar = process_vmar.unwrap();
 
     // After we clear process vm, if any error happens, we must call exit_group instead of return to user space.
+
+    // The executable is mapped before the interpreter, so that the randomized address of a
+    // position-independent executable will not conflict with the mapping of the interpreter.
+    let elf_load_bias = {
+        // Like Linux, a position-independent executable is mapped at `ELF_ET_DYN_BASE` if it
+        // requests an interpreter. Otherwise, it is likely to be the interpreter itself, so it is
+        // mapped wherever the allocator decides, like an `mmap`ed file.
+        let preferred_base = if ldso.is_some() {
+            Some(elf_et_dyn_base())
+        } else {
+            None
+        };
+        map_segment_vmos(parsed_elf, root_vmar, elf_file, preferred_base)?
+    };
+
+    // Like Linux, the heap is placed after the executable rather than the interpreter.
+    let (_, program_end) = load_segments_range(parsed_elf);
+    process_vm
+        .heap()
+        .init(root_vmar, program_end + elf_load_bias);
+
     let ldso_load_info = if let Some((ldso_file, ldso_elf)) = ldso {
         Some(load_ldso(root_vmar, &ldso_file, &ldso_elf)?)
     } else {
         None
     };
 
-    let elf_map_addr = map_segment_vmos(parsed_elf, root_vmar, elf_file)?;
-
     let aux_vec = {
         let ldso_base = ldso_load_info
             .as_ref()
             .map(|load_info| load_info.base_addr());
-        init_aux_vec(parsed_elf, elf_map_addr, ldso_base)?
+        init_aux_vec(parsed_elf, elf_load_bias, ldso_base, credentials)?
     };
 
     let entry_point = if let Some(ldso_load_info) = ldso_load_info {
-        // Normal shared object
+        // Dynamically linked executable
         ldso_load_info.entry_point()
-    } else if parsed_elf.is_shared_object() {
-        // ldso itself
-        parsed_elf.entry_point() + elf_map_addr
     } else {
-        // statically linked executable
-        parsed_elf.entry_point()
+        // Statically linked executable (the load bias is zero unless it is position-independent)
+        parsed_elf.entry_point() + elf_load_bias
     };
 
     Ok((entry_point, aux_vec))
 }
 
+/// Returns the randomized base address of position-independent executables.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/arch/x86/include/asm/elf.h#L253>.
+fn elf_et_dyn_base() -> Vaddr {
+    /// The number of random bits used for the base address, i.e., `mmap_rnd_bits` in Linux.
+    const NR_RANDOM_BITS: u32 = 28;
+
+    let mut random_value: u32 = 0;
+    getrandom(random_value.as_bytes_mut()).unwrap();
+    let nr_random_pages = random_value as usize & ((1 << NR_RANDOM_BITS) - 1);
+
+    (MAX_USERSPACE_VADDR / 3 * 2).align_down(PAGE_SIZE) + nr_random_pages * PAGE_SIZE
+}
+
 pub struct LdsoLoadInfo {
     entry_point: Vaddr,
     base_addr: Vaddr,
@@ -200,12 +236,24 @@ impl ElfLoadInfo {
     }
 }
 
-/// Inits VMO for each segment and then map segment to root vmar
-pub fn map_segment_vmos(elf: &Elf, root_vmar: &Vmar<Full>, elf_file: &Dentry) -> Result<Vaddr> {
+/// Inits VMO for each segment and then map segment to root vmar.
+///
+/// This function returns the load bias, i.e., the difference between the mapped addresses and
+/// the virtual addresses specified in the ELF file. The load bias is always zero for
+/// non-position-independent executables.
+///
+/// The segments of a shared object are mapped at `preferred_base` if it is given. Otherwise,
+/// they are mapped wherever the allocator decides.
+pub fn map_segment_vmos(
+    elf: &Elf,
+    root_vmar: &Vmar<Full>,
+    elf_file: &Dentry,
+    preferred_base: Option<Vaddr>,
+) -> Result<Vaddr> {
     // all segments of the shared object must be mapped to a continuous vm range
     // to ensure the relative offset of each segment not changed.
-    let base_addr = if elf.is_shared_object() {
-        base_map_addr(elf, root_vmar)?
+    let load_bias = if elf.is_shared_object() {
+        load_bias(elf, root_vmar, preferred_base)?
     } else {
         0
     };
@@ -213,38 +261,67 @@ pub fn map_segment_vmos(elf: &Elf, root_vmar: &Vmar<Full>, elf_file: &Dentry) ->
         let type_ = program_header
             .get_type()
             .map_err(|_| Error::with_message(Errno::ENOEXEC, "parse program header type fails"))?;
-        if type_ == program::Type::Load {
-            check_segment_align(program_header)?;
-            map_segment_vmo(program_header, elf_file, root_vmar, base_addr)?;
+        match type_ {
+            program::Type::Load => {
+                check_segment_align(program_header)?;
+                map_segment_vmo(program_header, elf_file, root_vmar, load_bias)?;
+            }
+            program::Type::Tls => check_tls_segment(program_header)?,
+            _ => (),
         }
     }
-    Ok(base_addr)
+    Ok(load_bias)
 }
 
-fn base_map_addr(elf: &Elf, root_vmar: &Vmar<Full>) -> Result<Vaddr> {
-    let elf_size = elf
-        .program_headers
-        .iter()
-        .filter_map(|program_header| {
-            if let Ok(type_) = program_header.get_type()
-                && type_ == program::Type::Load
-            {
-                let ph_max_addr = program_header.virtual_addr + program_header.mem_size;
-                Some(ph_max_addr as usize)
-            } else {
-                None
-            }
-        })
-        .max()
-        .ok_or(Error::with_message(
+/// Reserves a continuous VM range for all loadable segments and returns the load bias.
+fn load_bias(elf: &Elf, root_vmar: &Vmar<Full>, preferred_base: Option<Vaddr>) -> Result<Vaddr> {
+    let (min_addr, max_addr) = load_segments_range(elf);
+    if min_addr >= max_addr {
+        return_errno_with_message!(
             Errno::ENOEXEC,
-            "executable file does not has loadable sections",
-        ))?;
-    let map_size = elf_size.align_up(PAGE_SIZE);
+            "executable file does not has loadable sections"
+        );
+    }
+
+    let min_addr = min_addr.align_down(PAGE_SIZE);
+    let map_size = max_addr
+        .checked_next_multiple_of(PAGE_SIZE)
+        .filter(|max_addr| *max_addr <= MAX_USERSPACE_VADDR)
+        .ok_or_else(|| {
+            Error::with_message(Errno::ENOEXEC, "the loadable sections are too large")
+        })?
+        - min_addr;
+
     let vmar_map_options = root_vmar
         .new_map(map_size, VmPerms::empty())?
         .handle_page_faults_around();
-    vmar_map_options.build()
+    let map_addr = match preferred_base {
+        // Fall back to the allocator if the preferred range is unavailable.
+        Some(base) => match vmar_map_options.offset(base).build() {
+            Ok(map_addr) => map_addr,
+            Err(_) => root_vmar
+                .new_map(map_size, VmPerms::empty())?
+                .handle_page_faults_around()
+                .build()?,
+        },
+        None => vmar_map_options.build()?,
+    };
+
+    Ok(map_addr - min_addr)
+}
+
+/// Returns the lowest and highest addresses of all loadable segments before relocation.
+fn load_segments_range(elf: &Elf) -> (Vaddr, Vaddr) {
+    let load_segments = elf.program_headers.iter().filter(|program_header| {
+        program_header
+            .get_type()
+            .is_ok_and(|type_| type_ == program::Type::Load)
+    });
+    load_segments.fold((usize::MAX, 0), |(min, max), program_header| {
+        let start = program_header.virtual_addr as usize;
+        let end = start.saturating_add(program_header.mem_size as usize);
+        (min.min(start), max.max(end))
+    })
 }
 
 /// Creates and map the corresponding segment VMO to `root_vmar`.
@@ -270,7 +347,15 @@ fn map_segment_vmo(
 
     let file_offset = program_header.offset as usize;
     let virtual_addr = program_header.virtual_addr as usize;
-    debug_assert!(file_offset % PAGE_SIZE == virtual_addr % PAGE_SIZE);
+    if file_offset % PAGE_SIZE != virtual_addr % PAGE_SIZE {
+        return_errno_with_message!(
+            Errno::ENOEXEC,
+            "the segment offset and address are not congruent"
+        );
+    }
+    if program_header.file_size > program_header.mem_size {
+        return_errno_with_message!(Errno::ENOEXEC, "the segment file size is too large");
+    }
     let segment_vmo = {
         let inode = elf_file.inode();
         inode
@@ -302,6 +387,7 @@ fn map_segment_vmo(
         let mut vm_map_options = root_vmar
             .new_map(segment_size, perms)?
             .vmo(segment_vmo.dup()?)
+            .mapped_file(elf_file.clone())
             .vmo_offset(segment_offset)
             .vmo_limit(segment_offset + segment_size)
             .can_overwrite(true);
@@ -333,7 +419,7 @@ fn map_segment_vmo(
                 new_frame
             };
             cursor.map(
-                new_frame.into(),
+                new_frame,
                 PageProperty::new(page_flags, CachePolicy::Writeback),
             );
         }
@@ -358,7 +444,7 @@ fn map_segment_vmo(
             let tail_page_addr = map_addr + tail_padding_offset.align_down(PAGE_SIZE);
             cursor.jump(tail_page_addr)?;
             cursor.map(
-                new_frame.into(),
+                new_frame,
                 PageProperty::new(page_flags, CachePolicy::Writeback),
             );
         }
@@ -404,28 +490,57 @@ fn check_segment_align(program_header: &ProgramHeader64) -> Result<()> {
     Ok(())
 }
 
-pub fn init_aux_vec(elf: &Elf, elf_map_addr: Vaddr, ldso_base: Option<Vaddr>) -> Result<AuxVec> {
+/// Checks whether the TLS segment can be used as the initial TLS image.
+///
+/// The TLS blocks are set up by the user space runtime (e.g., the dynamic linker), but the
+/// kernel rejects the malformed TLS segments early, as it does for the loadable segments.
+fn check_tls_segment(program_header: &ProgramHeader64) -> Result<()> {
+    let align = program_header.align;
+    if align != 0 && !align.is_power_of_two() {
+        return_errno_with_message!(Errno::ENOEXEC, "TLS segment align is invalid.");
+    }
+    if program_header.file_size > program_header.mem_size {
+        return_errno_with_message!(Errno::ENOEXEC, "TLS segment file size is too large.");
+    }
+    Ok(())
+}
+
+pub fn init_aux_vec(
+    elf: &Elf,
+    elf_load_bias: Vaddr,
+    ldso_base: Option<Vaddr>,
+    credentials: &Credentials<ReadOp>,
+) -> Result<AuxVec> {
+    /// The frequency at which `times()` increments, i.e., `USER_HZ` in Linux.
+    const CLOCK_TICKS_PER_SEC: u64 = 100;
+
     let mut aux_vec = AuxVec::new();
     aux_vec.set(AuxKey::AT_PAGESZ, PAGE_SIZE as _)?;
-    let ph_addr = if elf.is_shared_object() {
-        elf.ph_addr()? + elf_map_addr
-    } else {
-        elf.ph_addr()?
-    };
+    aux_vec.set(AuxKey::AT_CLKTCK, CLOCK_TICKS_PER_SEC)?;
+    let ph_addr = elf.ph_addr()? + elf_load_bias;
     aux_vec.set(AuxKey::AT_PHDR, ph_addr as u64)?;
     aux_vec.set(AuxKey::AT_PHNUM, elf.ph_count() as u64)?;
     aux_vec.set(AuxKey::AT_PHENT, elf.ph_ent() as u64)?;
-    let elf_entry = if elf.is_shared_object() {
-        let base_load_offset = elf.base_load_address_offset();
-        elf.entry_point() + elf_map_addr - base_load_offset as usize
-    } else {
-        elf.entry_point()
-    };
+    aux_vec.set(AuxKey::AT_FLAGS, 0)?;
+    let elf_entry = elf.entry_point() + elf_load_bias;
     aux_vec.set(AuxKey::AT_ENTRY, elf_entry as u64)?;
 
     if let Some(ldso_base) = ldso_base {
         aux_vec.set(AuxKey::AT_BASE, ldso_base as u64)?;
     }
+
+    let ruid = credentials.ruid();
+    let euid = credentials.euid();
+    let rgid = credentials.rgid();
+    let egid = credentials.egid();
+    aux_vec.set(AuxKey::AT_UID, u32::from(ruid) as u64)?;
+    aux_vec.set(AuxKey::AT_EUID, u32::from(euid) as u64)?;
+    aux_vec.set(AuxKey::AT_GID, u32::from(rgid) as u64)?;
+    aux_vec.set(AuxKey::AT_EGID, u32::from(egid) as u64)?;
+    // The dynamic linker ignores environment variables such as `LD_PRELOAD` in secure mode.
+    let is_secure = ruid != euid || rgid != egid;
+    aux_vec.set(AuxKey::AT_SECURE, is_secure as u64)?;
+
     Ok(aux_vec)
 }
 
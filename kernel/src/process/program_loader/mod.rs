@@ -3,11 +3,13 @@
 pub mod elf;
 mod shebang;
 
+use aster_rights::ReadOp;
+
 use self::{
     elf::{load_elf_to_vm, ElfLoadInfo},
     shebang::parse_shebang_line,
 };
-use super::process_vm::ProcessVm;
+use super::{process_vm::ProcessVm, Credentials};
 use crate::{
     fs::{
         fs_resolver::{FsPath, FsResolver, AT_FDCWD},
@@ -80,6 +82,9 @@ impl ProgramToLoad {
 
     /// Loads the executable into the specified virtual memory space.
     ///
+    /// The `credentials` should be the credentials of the process after the executable is
+    /// loaded, i.e., with the set-user-ID and set-group-ID bits already applied.
+    ///
     /// Returns a tuple containing:
     /// 1. The absolute path of the loaded executable.
     /// 2. Information about the ELF loading process.
@@ -87,6 +92,7 @@ impl ProgramToLoad {
         self,
         process_vm: &ProcessVm,
         fs_resolver: &FsResolver,
+        credentials: &Credentials<ReadOp>,
     ) -> Result<(String, ElfLoadInfo)> {
         let abs_path = self.elf_file.abs_path();
         let elf_load_info = load_elf_to_vm(
@@ -96,6 +102,7 @@ impl ProgramToLoad {
             fs_resolver,
             self.argv,
             self.envp,
+            credentials,
         )?;
 
         Ok((abs_path, elf_load_info))
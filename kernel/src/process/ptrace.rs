@@ -0,0 +1,675 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Process tracing, i.e., the core of `ptrace`.
+//!
+//! A tracer (a process) traces a tracee (a thread). The tracee enters a ptrace-stop when it is
+//! about to handle a signal, when it executes a new program, and at each system call entry and
+//! exit if the tracer asks for it. The tracer is notified like for a stopped child, waits for
+//! the stop with `wait4`, inspects or modifies the tracee, and then resumes it.
+//!
+//! A tracee attached with `PTRACE_SEIZE` is not stopped by the attachment, and the tracer can
+//! stop it at any time with `PTRACE_INTERRUPT`.
+//!
+//! Only the main threads of processes can be traced for now, so a tracee can be identified by
+//! the process ID in `wait4`.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use ostd::{cpu::context::UserContext, sync::WaitQueue};
+
+use super::{
+    credentials::capabilities::CapSet,
+    posix_thread::{AsPosixThread, PosixThread},
+    signal::{
+        c_types::siginfo_t,
+        constants::{SIGKILL, SIGSTOP, SIGTRAP},
+        sig_num::SigNum,
+        signals::{kernel::KernelSignal, Signal},
+    },
+    wait::notify_child_stopped,
+    Process,
+};
+use crate::{
+    arch::ptrace::UserRegs,
+    cpu::LinuxAbi,
+    prelude::*,
+    thread::{AsThread, Thread, Tid},
+};
+
+bitflags! {
+    /// The options set by `PTRACE_SETOPTIONS`.
+    pub struct PtraceOptions: u32 {
+        /// Reports the system call stops with `SIGTRAP | 0x80`.
+        const TRACESYSGOOD = 0x1;
+        /// Stops at the next successful `execve` with `PTRACE_EVENT_EXEC`.
+        const TRACEEXEC = 0x10;
+        /// Kills the tracee if the tracer exits.
+        const EXITKILL = 0x100000;
+    }
+}
+
+const PTRACE_EVENT_EXEC: u32 = 4;
+const PTRACE_EVENT_STOP: u32 = 128;
+
+/// The ptrace state of a thread.
+pub struct Tracee {
+    state: SpinLock<Option<TraceState>>,
+    /// The wait queue where the tracee waits to be resumed in a ptrace-stop.
+    wait_queue: WaitQueue,
+    /// Whether the tracer has interrupted the tracee with `PTRACE_INTERRUPT`, which has not
+    /// stopped yet.
+    is_interrupted: AtomicBool,
+}
+
+struct TraceState {
+    /// The tracer, which is reset when the tracer detaches or exits.
+    tracer: Weak<Process>,
+    /// Whether the tracee is attached by `PTRACE_SEIZE`.
+    is_seized: bool,
+    options: PtraceOptions,
+    /// Whether the tracee stops at the system call entries and exits.
+    is_tracing_syscalls: bool,
+    /// The message of the last ptrace event, which is returned by `PTRACE_GETEVENTMSG`.
+    event_msg: usize,
+    stop: Option<PtraceStop>,
+}
+
+/// A ptrace-stop of a tracee.
+struct PtraceStop {
+    /// The status reported in bits 8..16 (and bits 16..24 for ptrace events) of the wait status.
+    status: u32,
+    is_reported: bool,
+    /// The user context of the tracee, which is copied back when the tracee is resumed.
+    user_ctx: Box<UserContext>,
+    /// The system call that the tracee is in, if the tracee is stopped at a system call entry or
+    /// exit. It can be changed by the tracer at a system call entry.
+    orig_syscall_num: Option<usize>,
+    /// The information of the signal, if the tracee is stopped at a signal delivery.
+    siginfo: Option<siginfo_t>,
+    is_resumed: bool,
+    /// The signal to deliver after the tracee is resumed.
+    resume_signal: Option<SigNum>,
+}
+
+/// How a tracee continues after being resumed from a ptrace-stop.
+struct Resumption {
+    orig_syscall_num: Option<usize>,
+    signal: Option<SigNum>,
+}
+
+impl Tracee {
+    pub(super) fn new() -> Self {
+        Self {
+            state: SpinLock::new(None),
+            wait_queue: WaitQueue::new(),
+            is_interrupted: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns whether the thread is being traced.
+    pub fn is_traced(&self) -> bool {
+        self.state
+            .lock()
+            .as_ref()
+            .is_some_and(|state| state.tracer.strong_count() > 0)
+    }
+
+    /// Returns whether the tracee has been interrupted by the tracer and should stop.
+    pub(super) fn is_interrupted(&self) -> bool {
+        self.is_interrupted.load(Ordering::Relaxed)
+    }
+
+    fn is_tracing_syscalls(&self) -> bool {
+        self.state
+            .lock()
+            .as_ref()
+            .is_some_and(|state| state.is_tracing_syscalls)
+    }
+
+    /// Wakes up the tracee if it is in a ptrace-stop, so that it can handle `SIGKILL`.
+    pub(super) fn wake_up(&self) {
+        self.wait_queue.wake_all();
+    }
+
+    /// Enters a ptrace-stop and waits until the tracer resumes the tracee.
+    ///
+    /// This method must be called by the tracee itself. It returns `None` if the thread is not
+    /// being traced or it is woken up by `SIGKILL`.
+    fn stop(
+        &self,
+        ctx: &Context,
+        user_ctx: &mut UserContext,
+        status: u32,
+        orig_syscall_num: Option<usize>,
+        siginfo: Option<siginfo_t>,
+    ) -> Option<Resumption> {
+        let tracer = {
+            let mut state = self.state.lock();
+            let state = state.as_mut()?;
+            let tracer = state.tracer.upgrade()?;
+            state.stop = Some(PtraceStop {
+                status,
+                is_reported: false,
+                user_ctx: Box::new(user_ctx.clone()),
+                orig_syscall_num,
+                siginfo,
+                is_resumed: false,
+                resume_signal: None,
+            });
+            tracer
+        };
+
+        // A ptrace-stop is a stop of the thread, just like a stop by `SIGSTOP`. But the tracee is
+        // resumed only by the tracer (or by `SIGKILL`), not by `SIGCONT`.
+        let has_stopped = ctx.thread.stop().is_ok();
+        notify_child_stopped(&tracer);
+        drop(tracer);
+
+        let stop = self.wait_queue.wait_until(|| {
+            let is_killed = ctx.posix_thread.is_killed();
+            let mut state = self.state.lock();
+            let state = state.as_mut().unwrap();
+            if is_killed || state.stop.as_ref().unwrap().is_resumed {
+                return state.stop.take();
+            }
+            None
+        });
+
+        if has_stopped {
+            ctx.thread.resume();
+        }
+
+        // The tracer has detached or exited.
+        let mut state = self.state.lock();
+        if state
+            .as_ref()
+            .is_some_and(|state| state.tracer.strong_count() == 0)
+        {
+            *state = None;
+        }
+        drop(state);
+
+        if !stop.is_resumed {
+            return None;
+        }
+        *user_ctx = *stop.user_ctx;
+        Some(Resumption {
+            orig_syscall_num: stop.orig_syscall_num,
+            signal: stop.resume_signal,
+        })
+    }
+
+    /// Takes the status of the ptrace-stop that has not been reported by `wait4`.
+    ///
+    /// The status is still reported next time if `is_nowait` is true.
+    pub(super) fn wait_stop_status(&self, is_nowait: bool) -> Option<u32> {
+        let mut state = self.state.lock();
+        let stop = state.as_mut()?.stop.as_mut()?;
+        if stop.is_reported || stop.is_resumed {
+            return None;
+        }
+        if !is_nowait {
+            stop.is_reported = true;
+        }
+        Some(stop.status)
+    }
+
+    /// Starts being traced by the tracer.
+    fn attach(&self, tracer: &Arc<Process>, is_seized: bool, options: PtraceOptions) -> Result<()> {
+        let mut state = self.state.lock();
+        if state.is_some() {
+            return_errno_with_message!(Errno::EPERM, "the thread is already being traced");
+        }
+
+        *state = Some(TraceState {
+            tracer: Arc::downgrade(tracer),
+            is_seized,
+            options,
+            is_tracing_syscalls: false,
+            event_msg: 0,
+            stop: None,
+        });
+        Ok(())
+    }
+
+    /// Interrupts the running tracee so that it enters a `PTRACE_EVENT_STOP` stop.
+    ///
+    /// Returns whether the tracee should be woken up. Interrupting a tracee that is already in a
+    /// ptrace-stop has no effect.
+    fn interrupt(&self, tracer: &Process) -> Result<bool> {
+        let state = self.state.lock();
+        let Some(state) = state
+            .as_ref()
+            .filter(|state| core::ptr::eq(state.tracer.as_ptr(), tracer))
+        else {
+            return_errno_with_message!(Errno::ESRCH, "the thread is not traced by the process");
+        };
+        if !state.is_seized {
+            return_errno_with_message!(
+                Errno::EIO,
+                "only the tracees attached by PTRACE_SEIZE can be interrupted"
+            );
+        }
+        if state.stop.as_ref().is_some_and(|stop| !stop.is_resumed) {
+            return Ok(false);
+        }
+
+        self.is_interrupted.store(true, Ordering::Relaxed);
+        Ok(true)
+    }
+
+    /// Operates on the state of the tracee that is traced by the tracer and is in a ptrace-stop.
+    fn with_stopped_state<R>(
+        &self,
+        tracer: &Process,
+        op: impl FnOnce(&mut TraceState) -> Result<R>,
+    ) -> Result<R> {
+        let mut state = self.state.lock();
+        let Some(state) = state
+            .as_mut()
+            .filter(|state| core::ptr::eq(state.tracer.as_ptr(), tracer))
+        else {
+            return_errno_with_message!(Errno::ESRCH, "the thread is not traced by the process");
+        };
+        if state.stop.as_ref().is_none_or(|stop| stop.is_resumed) {
+            return_errno_with_message!(Errno::ESRCH, "the tracee is not in a ptrace-stop");
+        }
+        op(state)
+    }
+
+    /// Checks that the tracee is traced by the tracer and is in a ptrace-stop.
+    pub fn check_stopped(&self, tracer: &Process) -> Result<()> {
+        self.with_stopped_state(tracer, |_| Ok(()))
+    }
+
+    /// Returns the registers of the stopped tracee.
+    pub fn regs(&self, tracer: &Process) -> Result<UserRegs> {
+        self.with_stopped_state(tracer, |state| {
+            let stop = state.stop.as_ref().unwrap();
+            Ok(UserRegs::from_context(
+                &stop.user_ctx,
+                stop.orig_syscall_num,
+            ))
+        })
+    }
+
+    /// Sets the registers of the stopped tracee.
+    pub fn set_regs(&self, tracer: &Process, regs: &UserRegs) -> Result<()> {
+        self.with_stopped_state(tracer, |state| {
+            let stop = state.stop.as_mut().unwrap();
+            regs.copy_to_context(&mut stop.user_ctx, &mut stop.orig_syscall_num);
+            Ok(())
+        })
+    }
+
+    /// Returns the information of the signal that stops the tracee.
+    pub fn siginfo(&self, tracer: &Process) -> Result<siginfo_t> {
+        self.with_stopped_state(tracer, |state| {
+            state.stop.as_ref().unwrap().siginfo.ok_or_else(|| {
+                Error::with_message(Errno::EINVAL, "the tracee is not stopped by a signal")
+            })
+        })
+    }
+
+    /// Returns the message of the last ptrace event.
+    pub fn event_msg(&self, tracer: &Process) -> Result<usize> {
+        self.with_stopped_state(tracer, |state| Ok(state.event_msg))
+    }
+
+    /// Sets the ptrace options.
+    pub fn set_options(&self, tracer: &Process, options: PtraceOptions) -> Result<()> {
+        self.with_stopped_state(tracer, |state| {
+            state.options = options;
+            Ok(())
+        })
+    }
+
+    /// Resumes the stopped tracee with the signal to deliver.
+    ///
+    /// If `is_tracing_syscalls` is true, the tracee will stop at the next system call entry or
+    /// exit.
+    pub fn resume(
+        &self,
+        tracer: &Process,
+        signal: Option<SigNum>,
+        is_tracing_syscalls: bool,
+    ) -> Result<()> {
+        self.with_stopped_state(tracer, |state| {
+            state.is_tracing_syscalls = is_tracing_syscalls;
+            let stop = state.stop.as_mut().unwrap();
+            stop.is_resumed = true;
+            stop.resume_signal = signal;
+            Ok(())
+        })?;
+        self.wait_queue.wake_all();
+        Ok(())
+    }
+
+    /// Stops being traced and resumes the tracee if it is stopped.
+    ///
+    /// The tracer must have been removed from the state by the caller.
+    fn release(&self, signal: Option<SigNum>) {
+        let mut state = self.state.lock();
+        let Some(trace_state) = state.as_mut() else {
+            return;
+        };
+        trace_state.tracer = Weak::new();
+        self.is_interrupted.store(false, Ordering::Relaxed);
+        match trace_state.stop.as_mut() {
+            Some(stop) if !stop.is_resumed => {
+                stop.is_resumed = true;
+                stop.resume_signal = signal;
+            }
+            // The tracee will clear the state after it is resumed.
+            Some(_) => (),
+            None => *state = None,
+        }
+        drop(state);
+
+        self.wait_queue.wake_all();
+    }
+}
+
+/// Makes the current thread traced by its parent (`PTRACE_TRACEME`).
+pub fn trace_me(ctx: &Context) -> Result<()> {
+    check_main_thread(ctx.posix_thread.tid(), ctx.process)?;
+
+    let Some(parent) = ctx.process.parent().lock().process().upgrade() else {
+        return_errno_with_message!(Errno::EPERM, "the process has no parent");
+    };
+    ctx.posix_thread
+        .tracee()
+        .attach(&parent, false, PtraceOptions::empty())?;
+    parent.tracees().lock().insert(
+        ctx.posix_thread.tid(),
+        ctx.task.as_thread().unwrap().clone(),
+    );
+    Ok(())
+}
+
+/// Makes the current process trace the thread (`PTRACE_ATTACH`).
+///
+/// The tracee will be stopped by `SIGSTOP`.
+pub fn attach(thread: &Arc<Thread>, ctx: &Context) -> Result<()> {
+    do_attach(thread, false, PtraceOptions::empty(), ctx)?;
+
+    let posix_thread = thread.as_posix_thread().unwrap();
+    posix_thread.enqueue_signal(Box::new(KernelSignal::new(SIGSTOP)));
+    Ok(())
+}
+
+/// Makes the current process trace the thread with the options (`PTRACE_SEIZE`).
+///
+/// Unlike `PTRACE_ATTACH`, the tracee is not stopped.
+pub fn seize(thread: &Arc<Thread>, options: PtraceOptions, ctx: &Context) -> Result<()> {
+    do_attach(thread, true, options, ctx)
+}
+
+fn do_attach(
+    thread: &Arc<Thread>,
+    is_seized: bool,
+    options: PtraceOptions,
+    ctx: &Context,
+) -> Result<()> {
+    let posix_thread = thread.as_posix_thread().unwrap();
+    let process = posix_thread.process();
+    check_main_thread(posix_thread.tid(), &process)?;
+
+    if core::ptr::eq(process.as_ref(), ctx.process) {
+        return_errno_with_message!(Errno::EPERM, "a process cannot trace itself");
+    }
+
+    check_access(ctx, posix_thread)?;
+
+    let tracer = ctx.posix_thread.process();
+    posix_thread.tracee().attach(&tracer, is_seized, options)?;
+    tracer
+        .tracees()
+        .lock()
+        .insert(posix_thread.tid(), thread.clone());
+    Ok(())
+}
+
+/// Stops the running tracee that is attached by `PTRACE_SEIZE` (`PTRACE_INTERRUPT`).
+///
+/// The tracee will enter the `PTRACE_EVENT_STOP` stop.
+pub fn interrupt(thread: &Arc<Thread>, ctx: &Context) -> Result<()> {
+    let posix_thread = thread.as_posix_thread().unwrap();
+    if posix_thread.tracee().interrupt(ctx.process)? {
+        posix_thread.wake_up_interruptible();
+    }
+    Ok(())
+}
+
+/// Stops tracing the stopped thread and resumes it with the signal (`PTRACE_DETACH`).
+pub fn detach(thread: &Arc<Thread>, signal: Option<SigNum>, ctx: &Context) -> Result<()> {
+    let posix_thread = thread.as_posix_thread().unwrap();
+    let tracee = posix_thread.tracee();
+    tracee.check_stopped(ctx.process)?;
+
+    ctx.process.tracees().lock().remove(&posix_thread.tid());
+    tracee.release(signal);
+    Ok(())
+}
+
+/// Checks whether the current thread is allowed to inspect the target thread, e.g., by tracing
+/// it or comparing its resources with `kcmp`.
+///
+/// The `CAP_SYS_PTRACE` capability is checked in the user namespace of the target thread.
+pub fn check_access(ctx: &Context, target: &PosixThread) -> Result<()> {
+    let credentials = ctx.posix_thread.credentials();
+    let target_credentials = target.credentials();
+    if credentials.has_capability_in(CapSet::SYS_PTRACE, &target_credentials.user_ns()) {
+        return Ok(());
+    }
+
+    let (uid, gid) = (credentials.ruid(), credentials.rgid());
+    if target_credentials.ruid() != uid
+        || target_credentials.euid() != uid
+        || target_credentials.suid() != uid
+        || target_credentials.rgid() != gid
+        || target_credentials.egid() != gid
+        || target_credentials.sgid() != gid
+    {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "accessing the processes of other users requires the CAP_SYS_PTRACE capability"
+        );
+    }
+    Ok(())
+}
+
+fn check_main_thread(tid: Tid, process: &Process) -> Result<()> {
+    if tid != process.pid() {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "tracing threads other than the main threads is not supported"
+        );
+    }
+    Ok(())
+}
+
+/// Enters the signal-delivery-stop if the current thread is traced.
+///
+/// Returns the signal to deliver after the stop, which is chosen by the tracer.
+pub fn stop_at_signal(
+    ctx: &Context,
+    user_ctx: &mut UserContext,
+    signal: Box<dyn Signal>,
+) -> Option<Box<dyn Signal>> {
+    let sig_num = signal.num();
+    let tracee = ctx.posix_thread.tracee();
+    if sig_num == SIGKILL || !tracee.is_traced() {
+        return Some(signal);
+    }
+
+    let resumption = tracee.stop(
+        ctx,
+        user_ctx,
+        sig_num.as_u8() as u32,
+        None,
+        Some(signal.to_info()),
+    );
+    let Some(resumption) = resumption else {
+        // The tracee has been killed. `SIGKILL` will be handled next.
+        return None;
+    };
+
+    match resumption.signal {
+        None => None,
+        Some(new_sig_num) if new_sig_num == sig_num => Some(signal),
+        Some(new_sig_num) => Some(Box::new(KernelSignal::new(new_sig_num))),
+    }
+}
+
+/// Enters the `PTRACE_EVENT_STOP` stop if the current thread is interrupted by the tracer.
+pub fn stop_at_interrupt(ctx: &Context, user_ctx: &mut UserContext) {
+    let tracee = ctx.posix_thread.tracee();
+    if !tracee.is_interrupted.swap(false, Ordering::Relaxed) {
+        return;
+    }
+
+    let status = SIGTRAP.as_u8() as u32 | (PTRACE_EVENT_STOP << 8);
+    if let Some(resumption) = tracee.stop(ctx, user_ctx, status, None, None) {
+        deliver_resume_signal(ctx, resumption.signal);
+    }
+}
+
+/// Enters the syscall-enter-stop if the current thread is traced with `PTRACE_SYSCALL`.
+///
+/// Returns whether the system call should be executed. The tracer may change the system call to
+/// be executed, or skip it.
+pub fn stop_at_syscall_entry(ctx: &Context, user_ctx: &mut UserContext) -> bool {
+    let tracee = ctx.posix_thread.tracee();
+    if !tracee.is_tracing_syscalls() {
+        return true;
+    }
+
+    let syscall_num = user_ctx.syscall_num();
+    // Like Linux, the return value seen by the tracer at the system call entry is `-ENOSYS`.
+    #[cfg(target_arch = "x86_64")]
+    user_ctx.set_syscall_ret(-(Errno::ENOSYS as i32) as usize);
+
+    let status = syscall_stop_status(tracee);
+    let Some(resumption) = tracee.stop(ctx, user_ctx, status, Some(syscall_num), None) else {
+        user_ctx.set_syscall_num(syscall_num);
+        return true;
+    };
+
+    deliver_resume_signal(ctx, resumption.signal);
+    match resumption.orig_syscall_num {
+        Some(syscall_num) => {
+            user_ctx.set_syscall_num(syscall_num);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Enters the syscall-exit-stop if the current thread is traced with `PTRACE_SYSCALL`.
+pub fn stop_at_syscall_exit(ctx: &Context, user_ctx: &mut UserContext, syscall_num: usize) {
+    let tracee = ctx.posix_thread.tracee();
+    if !tracee.is_tracing_syscalls() || ctx.thread.is_exited() {
+        return;
+    }
+
+    let status = syscall_stop_status(tracee);
+    if let Some(resumption) = tracee.stop(ctx, user_ctx, status, Some(syscall_num), None) {
+        deliver_resume_signal(ctx, resumption.signal);
+    }
+}
+
+/// Notifies the tracer that the current thread has executed a new program.
+///
+/// With `PTRACE_O_TRACEEXEC`, the tracee enters the `PTRACE_EVENT_EXEC` stop. Otherwise, it
+/// receives `SIGTRAP`, unless it is attached by `PTRACE_SEIZE`.
+pub fn notify_exec(ctx: &Context, user_ctx: &mut UserContext) {
+    let tracee = ctx.posix_thread.tracee();
+    let (is_tracing_exec, is_seized) = {
+        let mut state = tracee.state.lock();
+        let Some(state) = state
+            .as_mut()
+            .filter(|state| state.tracer.strong_count() > 0)
+        else {
+            return;
+        };
+        state.event_msg = ctx.posix_thread.tid() as usize;
+        (
+            state.options.contains(PtraceOptions::TRACEEXEC),
+            state.is_seized,
+        )
+    };
+
+    if !is_tracing_exec {
+        if !is_seized {
+            ctx.posix_thread
+                .enqueue_signal(Box::new(KernelSignal::new(SIGTRAP)));
+        }
+        return;
+    }
+
+    let status = SIGTRAP.as_u8() as u32 | (PTRACE_EVENT_EXEC << 8);
+    if let Some(resumption) = tracee.stop(ctx, user_ctx, status, None, None) {
+        deliver_resume_signal(ctx, resumption.signal);
+    }
+}
+
+fn syscall_stop_status(tracee: &Tracee) -> u32 {
+    let is_sysgood = tracee
+        .state
+        .lock()
+        .as_ref()
+        .is_some_and(|state| state.options.contains(PtraceOptions::TRACESYSGOOD));
+    if is_sysgood {
+        SIGTRAP.as_u8() as u32 | 0x80
+    } else {
+        SIGTRAP.as_u8() as u32
+    }
+}
+
+/// Delivers the signal chosen by the tracer when resuming from a stop that is not a
+/// signal-delivery-stop.
+fn deliver_resume_signal(ctx: &Context, signal: Option<SigNum>) {
+    if let Some(sig_num) = signal {
+        ctx.posix_thread
+            .enqueue_signal(Box::new(KernelSignal::new(sig_num)));
+    }
+}
+
+/// Stops tracing the current thread when it exits.
+pub(super) fn exit_tracee(thread: &Thread) {
+    let posix_thread = thread.as_posix_thread().unwrap();
+    let tracer = {
+        let mut state = posix_thread.tracee().state.lock();
+        state.take().and_then(|state| state.tracer.upgrade())
+    };
+    let Some(tracer) = tracer else {
+        return;
+    };
+
+    tracer.tracees().lock().remove(&posix_thread.tid());
+    // The tracer may be waiting for the tracee that has gone.
+    tracer.children_wait_queue().wake_all();
+}
+
+/// Stops tracing all the tracees of the current process when it exits.
+///
+/// The tracees are resumed, or killed if they have set `PTRACE_O_EXITKILL`.
+pub(super) fn exit_tracer(process: &Process) {
+    let tracees = core::mem::take(&mut *process.tracees().lock());
+    for thread in tracees.values() {
+        let posix_thread = thread.as_posix_thread().unwrap();
+        let tracee = posix_thread.tracee();
+
+        let is_exitkill = tracee
+            .state
+            .lock()
+            .as_ref()
+            .is_some_and(|state| state.options.contains(PtraceOptions::EXITKILL));
+        if is_exitkill {
+            posix_thread.enqueue_signal(Box::new(KernelSignal::new(SIGKILL)));
+        }
+
+        tracee.release(None);
+    }
+}
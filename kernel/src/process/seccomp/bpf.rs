@@ -0,0 +1,344 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The classic BPF programs for the seccomp filters.
+//!
+//! A program is checked and decoded when it is installed, so the interpreter never fails. Like
+//! Linux, the seccomp filters support only a subset of the classic BPF, which cannot load the
+//! packet data by bytes or halfwords, or at the offsets relative to the index register.
+//!
+//! Reference: <https://www.kernel.org/doc/Documentation/networking/filter.txt>
+
+use crate::prelude::*;
+
+/// The maximum number of the instructions in a program.
+pub(super) const BPF_MAXINSNS: usize = 4096;
+
+/// The number of the scratch memory words.
+const BPF_MEMWORDS: usize = 16;
+
+// The classes of the instructions.
+const BPF_LD: u16 = 0x00;
+const BPF_LDX: u16 = 0x01;
+const BPF_ST: u16 = 0x02;
+const BPF_STX: u16 = 0x03;
+const BPF_ALU: u16 = 0x04;
+const BPF_JMP: u16 = 0x05;
+const BPF_RET: u16 = 0x06;
+const BPF_MISC: u16 = 0x07;
+
+// Modes of the load instructions.
+const BPF_IMM: u16 = 0x00;
+const BPF_ABS: u16 = 0x20;
+const BPF_MEM: u16 = 0x60;
+const BPF_LEN: u16 = 0x80;
+
+// Operations of the ALU instructions.
+const BPF_ADD: u16 = 0x00;
+const BPF_SUB: u16 = 0x10;
+const BPF_MUL: u16 = 0x20;
+const BPF_DIV: u16 = 0x30;
+const BPF_OR: u16 = 0x40;
+const BPF_AND: u16 = 0x50;
+const BPF_LSH: u16 = 0x60;
+const BPF_RSH: u16 = 0x70;
+const BPF_NEG: u16 = 0x80;
+const BPF_MOD: u16 = 0x90;
+const BPF_XOR: u16 = 0xa0;
+
+// Operations of the jump instructions.
+const BPF_JA: u16 = 0x00;
+const BPF_JEQ: u16 = 0x10;
+const BPF_JGT: u16 = 0x20;
+const BPF_JGE: u16 = 0x30;
+const BPF_JSET: u16 = 0x40;
+
+// Sources of the ALU, jump, and return instructions.
+const BPF_K: u16 = 0x00;
+const BPF_X: u16 = 0x08;
+const BPF_A: u16 = 0x10;
+
+// Operations of the miscellaneous instructions.
+const BPF_TAX: u16 = 0x00;
+const BPF_TXA: u16 = 0x80;
+
+/// A classic BPF instruction in the user space (`struct sock_filter` in Linux).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct SockFilter {
+    code: u16,
+    jt: u8,
+    jf: u8,
+    k: u32,
+}
+
+/// A checked classic BPF program.
+#[derive(Debug)]
+pub(super) struct BpfProgram {
+    insns: Box<[Insn]>,
+}
+
+/// A decoded instruction.
+#[derive(Debug, Clone, Copy)]
+enum Insn {
+    /// Loads a word of the data at the offset into the accumulator.
+    LoadAbs(u32),
+    /// Loads a value into the accumulator.
+    Load(Operand),
+    /// Loads a value into the index register.
+    LoadX(Operand),
+    /// Stores the accumulator into the scratch memory.
+    Store(u32),
+    /// Stores the index register into the scratch memory.
+    StoreX(u32),
+    /// Computes the accumulator with the operation and the source.
+    Alu(AluOp, Src),
+    /// Negates the accumulator.
+    Neg,
+    /// Jumps forward unconditionally.
+    Jump(u32),
+    /// Jumps forward by `jt` if the condition holds, or by `jf` otherwise.
+    JumpIf {
+        cond: JumpCond,
+        src: Src,
+        jt: u8,
+        jf: u8,
+    },
+    /// Returns the value.
+    Ret(Operand),
+    /// Copies the accumulator into the index register.
+    Tax,
+    /// Copies the index register into the accumulator.
+    Txa,
+}
+
+/// An operand of the load and return instructions.
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+    Imm(u32),
+    /// The length of the data.
+    Len,
+    /// A word of the scratch memory.
+    Mem(u32),
+    /// The accumulator.
+    A,
+}
+
+/// A source of the ALU and jump instructions.
+#[derive(Debug, Clone, Copy)]
+enum Src {
+    K(u32),
+    X,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum AluOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Or,
+    And,
+    Xor,
+    Lsh,
+    Rsh,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum JumpCond {
+    Eq,
+    Gt,
+    Ge,
+    Set,
+}
+
+impl BpfProgram {
+    /// Checks and decodes a program that runs on the data of `data_len` bytes.
+    pub(super) fn new(filters: &[SockFilter], data_len: usize) -> Result<Self> {
+        if filters.is_empty() || filters.len() > BPF_MAXINSNS {
+            return_errno_with_message!(Errno::EINVAL, "the BPF program length is invalid");
+        }
+
+        let insns = filters
+            .iter()
+            .enumerate()
+            .map(|(pc, filter)| decode_insn(filter, filters.len() - pc - 1, data_len))
+            .collect::<Result<Box<[Insn]>>>()?;
+
+        if !matches!(insns.last(), Some(Insn::Ret(_))) {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the BPF program does not end with a return instruction"
+            );
+        }
+
+        Ok(Self { insns })
+    }
+
+    /// Returns the number of the instructions.
+    pub(super) fn len(&self) -> usize {
+        self.insns.len()
+    }
+
+    /// Runs the program on the data and returns its return value.
+    pub(super) fn run(&self, data: &[u8]) -> u32 {
+        let mut a: u32 = 0;
+        let mut x: u32 = 0;
+        let mut mem = [0u32; BPF_MEMWORDS];
+
+        let read_operand = |operand: Operand, a: u32, mem: &[u32]| match operand {
+            Operand::Imm(k) => k,
+            Operand::Len => data.len() as u32,
+            Operand::Mem(index) => mem[index as usize],
+            Operand::A => a,
+        };
+
+        // The jumps are always forward and are checked to be in the program, so the program
+        // terminates at a return instruction.
+        let mut pc = 0;
+        loop {
+            let insn = self.insns[pc];
+            pc += 1;
+
+            match insn {
+                Insn::LoadAbs(offset) => {
+                    let offset = offset as usize;
+                    let bytes = data[offset..offset + size_of::<u32>()].try_into().unwrap();
+                    a = u32::from_ne_bytes(bytes);
+                }
+                Insn::Load(operand) => a = read_operand(operand, a, &mem),
+                Insn::LoadX(operand) => x = read_operand(operand, a, &mem),
+                Insn::Store(index) => mem[index as usize] = a,
+                Insn::StoreX(index) => mem[index as usize] = x,
+                Insn::Alu(op, src) => {
+                    let operand = match src {
+                        Src::K(k) => k,
+                        Src::X => x,
+                    };
+                    a = match op {
+                        AluOp::Add => a.wrapping_add(operand),
+                        AluOp::Sub => a.wrapping_sub(operand),
+                        AluOp::Mul => a.wrapping_mul(operand),
+                        // Dividing by zero aborts the program with zero, as in Linux.
+                        AluOp::Div | AluOp::Mod if operand == 0 => return 0,
+                        AluOp::Div => a / operand,
+                        AluOp::Mod => a % operand,
+                        AluOp::Or => a | operand,
+                        AluOp::And => a & operand,
+                        AluOp::Xor => a ^ operand,
+                        AluOp::Lsh => a.checked_shl(operand).unwrap_or(0),
+                        AluOp::Rsh => a.checked_shr(operand).unwrap_or(0),
+                    };
+                }
+                Insn::Neg => a = a.wrapping_neg(),
+                Insn::Jump(offset) => pc += offset as usize,
+                Insn::JumpIf { cond, src, jt, jf } => {
+                    let operand = match src {
+                        Src::K(k) => k,
+                        Src::X => x,
+                    };
+                    let is_true = match cond {
+                        JumpCond::Eq => a == operand,
+                        JumpCond::Gt => a > operand,
+                        JumpCond::Ge => a >= operand,
+                        JumpCond::Set => a & operand != 0,
+                    };
+                    pc += if is_true { jt } else { jf } as usize;
+                }
+                Insn::Ret(operand) => return read_operand(operand, a, &mem),
+                Insn::Tax => x = a,
+                Insn::Txa => a = x,
+            }
+        }
+    }
+}
+
+/// Checks and decodes an instruction, which is followed by `nr_following` instructions.
+fn decode_insn(filter: &SockFilter, nr_following: usize, data_len: usize) -> Result<Insn> {
+    let SockFilter { code, jt, jf, k } = *filter;
+    if code > 0xff {
+        return_errno_with_message!(Errno::EINVAL, "the BPF instruction is invalid");
+    }
+
+    let mem_index = || {
+        if (k as usize) < BPF_MEMWORDS {
+            Ok(k)
+        } else {
+            Err(Error::with_message(
+                Errno::EINVAL,
+                "the BPF scratch memory index is out of range",
+            ))
+        }
+    };
+    let src = if code & BPF_X != 0 { Src::X } else { Src::K(k) };
+
+    // Only the word loads are supported, and `BPF_W` is zero, so the modes of the load
+    // instructions are matched directly.
+    let insn = match (code & 0x07, code & !0x07) {
+        (BPF_LD, BPF_ABS) => {
+            if k % 4 != 0 || k as usize + size_of::<u32>() > data_len {
+                return_errno_with_message!(Errno::EINVAL, "the BPF load offset is invalid");
+            }
+            Insn::LoadAbs(k)
+        }
+        (BPF_LD, BPF_IMM) => Insn::Load(Operand::Imm(k)),
+        (BPF_LD, BPF_MEM) => Insn::Load(Operand::Mem(mem_index()?)),
+        (BPF_LD, BPF_LEN) => Insn::Load(Operand::Len),
+        (BPF_LDX, BPF_IMM) => Insn::LoadX(Operand::Imm(k)),
+        (BPF_LDX, BPF_MEM) => Insn::LoadX(Operand::Mem(mem_index()?)),
+        (BPF_LDX, BPF_LEN) => Insn::LoadX(Operand::Len),
+        (BPF_ST, 0) => Insn::Store(mem_index()?),
+        (BPF_STX, 0) => Insn::StoreX(mem_index()?),
+        (BPF_ALU, BPF_NEG) => Insn::Neg,
+        (BPF_ALU, _) => {
+            let op = match code & 0xf0 {
+                BPF_ADD => AluOp::Add,
+                BPF_SUB => AluOp::Sub,
+                BPF_MUL => AluOp::Mul,
+                BPF_DIV => AluOp::Div,
+                BPF_MOD => AluOp::Mod,
+                BPF_OR => AluOp::Or,
+                BPF_AND => AluOp::And,
+                BPF_XOR => AluOp::Xor,
+                BPF_LSH => AluOp::Lsh,
+                BPF_RSH => AluOp::Rsh,
+                _ => return_errno_with_message!(Errno::EINVAL, "the BPF ALU is not supported"),
+            };
+            match (op, src) {
+                (AluOp::Div | AluOp::Mod, Src::K(0)) => {
+                    return_errno_with_message!(Errno::EINVAL, "the BPF program divides by zero")
+                }
+                (AluOp::Lsh | AluOp::Rsh, Src::K(k)) if k >= u32::BITS => {
+                    return_errno_with_message!(Errno::EINVAL, "the BPF shift is too large")
+                }
+                _ => Insn::Alu(op, src),
+            }
+        }
+        (BPF_JMP, BPF_JA) => {
+            if k as usize >= nr_following {
+                return_errno_with_message!(Errno::EINVAL, "the BPF jump is out of range");
+            }
+            Insn::Jump(k)
+        }
+        (BPF_JMP, _) => {
+            let cond = match code & 0xf0 {
+                BPF_JEQ => JumpCond::Eq,
+                BPF_JGT => JumpCond::Gt,
+                BPF_JGE => JumpCond::Ge,
+                BPF_JSET => JumpCond::Set,
+                _ => return_errno_with_message!(Errno::EINVAL, "the BPF jump is not supported"),
+            };
+            if jt as usize >= nr_following || jf as usize >= nr_following {
+                return_errno_with_message!(Errno::EINVAL, "the BPF jump is out of range");
+            }
+            Insn::JumpIf { cond, src, jt, jf }
+        }
+        (BPF_RET, BPF_K) => Insn::Ret(Operand::Imm(k)),
+        (BPF_RET, BPF_A) => Insn::Ret(Operand::A),
+        (BPF_MISC, BPF_TAX) => Insn::Tax,
+        (BPF_MISC, BPF_TXA) => Insn::Txa,
+        _ => return_errno_with_message!(Errno::EINVAL, "the BPF instruction is not supported"),
+    };
+
+    Ok(insn)
+}
@@ -0,0 +1,376 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Secure computing (seccomp).
+//!
+//! A thread in the strict mode can only make the `read`, `write`, `exit`, and `rt_sigreturn`
+//! system calls. A thread in the filter mode runs the installed BPF filters on each system call
+//! to decide whether the system call is allowed, fails with an error number, is passed to a
+//! user-space supervisor, or kills the thread or the process. The mode and the filters are
+//! inherited by the child threads and processes, and are preserved across `execve`. A thread can
+//! only add more restrictions to itself.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man2/seccomp.2.html>
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use atomic_integer_wrapper::define_atomic_version_of_integer_like_type;
+use bpf::BpfProgram;
+pub use bpf::SockFilter;
+pub use notify::{CSeccompNotifSizes, SeccompListener, SeccompNotifier, UserNotifResponse};
+
+use crate::{prelude::*, process::posix_thread::PosixThread, thread::Tid};
+
+mod bpf;
+mod notify;
+
+/// The seccomp mode of a thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+#[repr(u8)]
+pub enum SeccompMode {
+    Disabled = 0,
+    Strict = 1,
+    Filter = 2,
+}
+
+define_atomic_version_of_integer_like_type!(SeccompMode, try_from = true, {
+    #[derive(Debug)]
+    struct AtomicSeccompMode(AtomicU8);
+});
+
+impl From<SeccompMode> for u8 {
+    fn from(value: SeccompMode) -> Self {
+        value as _
+    }
+}
+
+/// The seccomp state of a POSIX thread.
+#[derive(Debug)]
+pub struct Seccomp {
+    /// The mode, which is read without the lock on each system call.
+    mode: AtomicSeccompMode,
+    /// The most recently installed filter, which is linked to the previous ones.
+    filter: SpinLock<Option<Arc<SeccompFilter>>>,
+}
+
+impl Seccomp {
+    /// Creates a state in which seccomp is disabled.
+    pub fn new() -> Self {
+        Self {
+            mode: AtomicSeccompMode::new(SeccompMode::Disabled),
+            filter: SpinLock::new(None),
+        }
+    }
+
+    /// Creates a state for a child thread, which inherits the mode and the filters.
+    pub fn new_inherited(&self) -> Self {
+        let filter = self.filter.lock();
+        Self {
+            mode: AtomicSeccompMode::new(self.mode()),
+            filter: SpinLock::new(filter.clone()),
+        }
+    }
+
+    /// Returns the mode.
+    pub fn mode(&self) -> SeccompMode {
+        self.mode.load(Ordering::Relaxed)
+    }
+
+    /// Switches to the strict mode.
+    ///
+    /// The strict mode cannot be entered if some filters have been installed.
+    pub fn set_mode_strict(&self) -> Result<()> {
+        let _filter = self.filter.lock();
+        match self.mode() {
+            SeccompMode::Disabled | SeccompMode::Strict => {
+                self.mode.store(SeccompMode::Strict, Ordering::Relaxed);
+                Ok(())
+            }
+            SeccompMode::Filter => {
+                return_errno_with_message!(Errno::EINVAL, "seccomp filters have been installed")
+            }
+        }
+    }
+
+    /// Installs a filter and switches to the filter mode.
+    ///
+    /// If `notifier` is specified, the notifications of the filter are passed to it.
+    pub fn add_filter(
+        &self,
+        program: SeccompProgram,
+        notifier: Option<Arc<SeccompNotifier>>,
+    ) -> Result<()> {
+        let mut filter = self.filter.lock();
+
+        self.check_filter_mode_allowed()?;
+        *filter = Some(SeccompFilter::new(program, filter.clone(), notifier)?);
+        self.mode.store(SeccompMode::Filter, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Installs a filter for `self` and the other threads, which must have installed the same
+    /// filters as `self` (or the earlier ones of them).
+    ///
+    /// If a thread does not satisfy the requirement, no filter is installed and the TID of the
+    /// thread is returned.
+    pub fn add_filter_synced(
+        &self,
+        program: SeccompProgram,
+        notifier: Option<Arc<SeccompNotifier>>,
+        other_threads: &[&PosixThread],
+    ) -> Result<core::result::Result<(), Tid>> {
+        let mut filter = self.filter.lock();
+
+        self.check_filter_mode_allowed()?;
+        let new_filter = SeccompFilter::new(program, filter.clone(), notifier)?;
+
+        let mut other_filters = Vec::with_capacity(other_threads.len());
+        for thread in other_threads {
+            let seccomp = thread.seccomp();
+            let other_filter = seccomp.filter.lock();
+            let is_synced = match seccomp.mode() {
+                SeccompMode::Disabled => true,
+                SeccompMode::Strict => false,
+                SeccompMode::Filter => filter
+                    .as_ref()
+                    .is_some_and(|filter| filter.is_descendant_of(other_filter.as_ref().unwrap())),
+            };
+            if !is_synced {
+                return Ok(Err(thread.tid()));
+            }
+            other_filters.push((seccomp, other_filter));
+        }
+
+        for (seccomp, mut other_filter) in other_filters {
+            *other_filter = Some(new_filter.clone());
+            seccomp.mode.store(SeccompMode::Filter, Ordering::Relaxed);
+        }
+        *filter = Some(new_filter);
+        self.mode.store(SeccompMode::Filter, Ordering::Relaxed);
+
+        Ok(Ok(()))
+    }
+
+    fn check_filter_mode_allowed(&self) -> Result<()> {
+        if self.mode() == SeccompMode::Strict {
+            return_errno_with_message!(Errno::EINVAL, "the thread is in the strict mode");
+        }
+        Ok(())
+    }
+
+    /// Runs the installed filters on the system call and returns the action to take.
+    ///
+    /// This method should only be called in the filter mode.
+    pub fn run_filters(&self, data: &SeccompData) -> SeccompAction {
+        let Some(newest_filter) = self.filter.lock().clone() else {
+            return SeccompAction::Allow;
+        };
+
+        // The action with the highest precedence is taken. The precedence is the reverse order
+        // of the actions, which are compared as signed integers. If multiple filters return the
+        // same action, the newest one is taken.
+        let mut ret = SECCOMP_RET_ALLOW;
+        let mut ret_filter = &newest_filter;
+        let mut filter = Some(&newest_filter);
+        while let Some(current) = filter {
+            let filter_ret = current.program.run(data.as_bytes());
+            if ((filter_ret & SECCOMP_RET_ACTION_FULL) as i32)
+                < ((ret & SECCOMP_RET_ACTION_FULL) as i32)
+            {
+                ret = filter_ret;
+                ret_filter = current;
+            }
+            filter = current.prev.as_ref();
+        }
+
+        SeccompAction::from_ret(ret, ret_filter)
+    }
+}
+
+impl Default for Seccomp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An installed filter.
+#[derive(Debug)]
+struct SeccompFilter {
+    program: BpfProgram,
+    /// The number of the instructions in this filter and the previous ones, where each filter
+    /// is counted with a penalty of [`FILTER_PENALTY_INSNS`] instructions.
+    nr_insns_in_path: usize,
+    prev: Option<Arc<SeccompFilter>>,
+    /// The notifier of `SECCOMP_RET_USER_NOTIF`, which exists if the filter has a listener.
+    notifier: Option<Arc<SeccompNotifier>>,
+}
+
+/// The maximum number of the instructions in all the filters of a thread.
+const MAX_INSNS_PER_PATH: usize = 32768;
+
+/// The number of the instructions that each filter is counted as besides its own instructions.
+const FILTER_PENALTY_INSNS: usize = 4;
+
+impl SeccompFilter {
+    fn new(
+        program: SeccompProgram,
+        prev: Option<Arc<SeccompFilter>>,
+        notifier: Option<Arc<SeccompNotifier>>,
+    ) -> Result<Arc<Self>> {
+        let program = program.0;
+
+        // Otherwise, it is unclear which supervisor a system call should be passed to.
+        if notifier.is_some()
+            && let Some(prev) = prev.as_ref()
+            && prev.has_notifier_in_path()
+        {
+            return_errno_with_message!(
+                Errno::EBUSY,
+                "a seccomp filter with a listener has been installed"
+            );
+        }
+
+        let nr_insns_in_path = program.len()
+            + FILTER_PENALTY_INSNS
+            + prev.as_ref().map_or(0, |prev| prev.nr_insns_in_path);
+        if nr_insns_in_path > MAX_INSNS_PER_PATH {
+            return_errno_with_message!(Errno::ENOMEM, "the seccomp filters are too long");
+        }
+
+        Ok(Arc::new(Self {
+            program,
+            nr_insns_in_path,
+            prev,
+            notifier,
+        }))
+    }
+
+    /// Returns whether this filter or one of the previous filters has a listener.
+    fn has_notifier_in_path(&self) -> bool {
+        let mut filter = Some(self);
+        while let Some(current) = filter {
+            if current.notifier.is_some() {
+                return true;
+            }
+            filter = current.prev.as_deref();
+        }
+        false
+    }
+
+    /// Returns whether `ancestor` is this filter or one of the previous filters.
+    fn is_descendant_of(self: &Arc<Self>, ancestor: &Arc<Self>) -> bool {
+        let mut filter = self;
+        loop {
+            if Arc::ptr_eq(filter, ancestor) {
+                return true;
+            }
+            let Some(prev) = filter.prev.as_ref() else {
+                return false;
+            };
+            filter = prev;
+        }
+    }
+}
+
+impl Drop for SeccompFilter {
+    fn drop(&mut self) {
+        if let Some(notifier) = self.notifier.as_ref() {
+            notifier.hang_up();
+        }
+    }
+}
+
+/// A BPF program that has been checked to be a valid seccomp filter.
+#[derive(Debug)]
+pub struct SeccompProgram(BpfProgram);
+
+impl SeccompProgram {
+    /// The maximum number of the instructions in a program.
+    pub const MAX_LEN: usize = bpf::BPF_MAXINSNS;
+
+    /// Checks the instructions and creates a program.
+    pub fn new(filters: &[SockFilter]) -> Result<Self> {
+        BpfProgram::new(filters, size_of::<SeccompData>()).map(Self)
+    }
+}
+
+/// The data that a filter runs on (`struct seccomp_data` in Linux).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct SeccompData {
+    /// The system call number.
+    pub nr: i32,
+    /// The `AUDIT_ARCH_*` value of the system call convention.
+    pub arch: u32,
+    /// The address of the instruction that follows the system call instruction.
+    pub instruction_pointer: u64,
+    /// The arguments of the system call.
+    pub args: [u64; 6],
+}
+
+/// The action to take on a system call, as returned by the filters.
+#[derive(Debug)]
+pub enum SeccompAction {
+    /// Kills the process as if by `SIGSYS`.
+    KillProcess,
+    /// Kills the thread as if by `SIGSYS`.
+    KillThread,
+    /// Sends `SIGSYS` to the thread, with the data as `si_errno`.
+    Trap(u16),
+    /// Fails the system call with the data as the error number.
+    Errno(u16),
+    /// Notifies the user-space supervisor, if the filter that returns the action has a listener.
+    UserNotif(Option<Arc<SeccompNotifier>>),
+    /// Notifies the tracer.
+    Trace,
+    /// Logs and allows the system call.
+    Log,
+    /// Allows the system call.
+    Allow,
+}
+
+const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+const SECCOMP_RET_KILL_THREAD: u32 = 0x0000_0000;
+const SECCOMP_RET_TRAP: u32 = 0x0003_0000;
+const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+const SECCOMP_RET_USER_NOTIF: u32 = 0x7fc0_0000;
+const SECCOMP_RET_TRACE: u32 = 0x7ff0_0000;
+const SECCOMP_RET_LOG: u32 = 0x7ffc_0000;
+const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+
+const SECCOMP_RET_ACTION_FULL: u32 = 0xffff_0000;
+const SECCOMP_RET_DATA: u32 = 0x0000_ffff;
+
+impl SeccompAction {
+    /// Decodes the return value of the filter.
+    ///
+    /// An unknown action is regarded as [`SeccompAction::KillProcess`], as in Linux.
+    fn from_ret(ret: u32, filter: &SeccompFilter) -> Self {
+        let data = (ret & SECCOMP_RET_DATA) as u16;
+        match ret & SECCOMP_RET_ACTION_FULL {
+            SECCOMP_RET_KILL_THREAD => Self::KillThread,
+            SECCOMP_RET_TRAP => Self::Trap(data),
+            SECCOMP_RET_ERRNO => Self::Errno(data),
+            SECCOMP_RET_USER_NOTIF => Self::UserNotif(filter.notifier.clone()),
+            SECCOMP_RET_TRACE => Self::Trace,
+            SECCOMP_RET_LOG => Self::Log,
+            SECCOMP_RET_ALLOW => Self::Allow,
+            _ => Self::KillProcess,
+        }
+    }
+
+    /// Returns whether the action (without the data) is supported.
+    pub fn is_available(action: u32) -> bool {
+        matches!(
+            action,
+            SECCOMP_RET_KILL_PROCESS
+                | SECCOMP_RET_KILL_THREAD
+                | SECCOMP_RET_TRAP
+                | SECCOMP_RET_ERRNO
+                | SECCOMP_RET_USER_NOTIF
+                | SECCOMP_RET_TRACE
+                | SECCOMP_RET_LOG
+                | SECCOMP_RET_ALLOW
+        )
+    }
+}
@@ -0,0 +1,536 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! User-space notifications.
+//!
+//! A filter installed with `SECCOMP_FILTER_FLAG_NEW_LISTENER` has a listener file. When the
+//! filter returns `SECCOMP_RET_USER_NOTIF`, the system call is sent to the supervisor that reads
+//! the listener file, and the thread waits until the supervisor responds with the return value of
+//! the system call or lets the system call continue. Meanwhile, the supervisor can install its
+//! files into the file table of the thread.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man2/seccomp_unotify.2.html>
+
+use core::{
+    fmt::Debug,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use ostd::{
+    sync::{RwArc, WaitQueue},
+    task::Task,
+};
+
+use super::SeccompData;
+use crate::{
+    current_userspace,
+    events::IoEvents,
+    fs::{
+        file_handle::FileLike,
+        file_table::{FdFlags, FileDesc, FileTable},
+        utils::{CreationFlags, InodeMode, IoctlCmd, Metadata},
+    },
+    prelude::*,
+    process::{
+        posix_thread::AsThreadLocal,
+        signal::{Pause, PollHandle, Pollable, Pollee},
+    },
+    thread::Tid,
+};
+
+/// The notifier of a filter, which passes the notifications to the supervisor.
+pub struct SeccompNotifier {
+    state: Mutex<NotifierState>,
+    /// The pollee of the listener file.
+    pollee: Pollee,
+    /// The wait queue for the threads that wait for the responses.
+    wait_queue: WaitQueue,
+    /// Whether the threads wait killably once their notifications are received.
+    is_wait_killable: bool,
+    /// Whether the filter is dropped, so no more notifications will be made.
+    is_hung_up: AtomicBool,
+}
+
+struct NotifierState {
+    /// The notifications that are not responded to yet, in the order they are made.
+    notifications: BTreeMap<u64, Notification>,
+    next_id: u64,
+    is_listener_closed: bool,
+}
+
+struct Notification {
+    tid: Tid,
+    data: SeccompData,
+    /// The file table of the thread, into which the supervisor can install files.
+    file_table: RwArc<FileTable>,
+    /// The `RLIMIT_NOFILE` limit of the thread.
+    nofile_limit: usize,
+    status: NotificationStatus,
+}
+
+#[derive(Clone, Copy)]
+enum NotificationStatus {
+    /// The notification has not been received by the supervisor.
+    Pending,
+    /// The notification has been received by the supervisor.
+    Received,
+    /// The supervisor has responded to the notification.
+    Replied(UserNotifResponse),
+}
+
+/// The response of the supervisor to a system call.
+#[derive(Debug, Clone, Copy)]
+pub enum UserNotifResponse {
+    /// The system call returns the value without being made.
+    Return(isize),
+    /// The system call is made as if it were allowed.
+    Continue,
+}
+
+impl SeccompNotifier {
+    /// Creates a notifier and its listener file.
+    pub fn new_with_listener(is_wait_killable: bool) -> (Arc<Self>, Arc<SeccompListener>) {
+        let notifier = Arc::new(Self {
+            state: Mutex::new(NotifierState {
+                notifications: BTreeMap::new(),
+                next_id: 1,
+                is_listener_closed: false,
+            }),
+            pollee: Pollee::new(),
+            wait_queue: WaitQueue::new(),
+            is_wait_killable,
+            is_hung_up: AtomicBool::new(false),
+        });
+        let listener = Arc::new(SeccompListener(notifier.clone()));
+
+        (notifier, listener)
+    }
+
+    /// Sends the system call to the supervisor and waits for the response.
+    ///
+    /// # Errors
+    ///
+    /// This method fails with `ENOSYS` if the listener file is closed, and with `ERESTARTSYS` if
+    /// the wait is interrupted by a signal, in which case the notification is withdrawn.
+    pub fn notify(
+        &self,
+        tid: Tid,
+        data: SeccompData,
+        file_table: RwArc<FileTable>,
+        nofile_limit: usize,
+    ) -> Result<UserNotifResponse> {
+        let id = {
+            let mut state = self.state.lock();
+            if state.is_listener_closed {
+                return_errno_with_message!(Errno::ENOSYS, "the seccomp listener is closed");
+            }
+
+            let id = state.next_id;
+            state.next_id += 1;
+            state.notifications.insert(
+                id,
+                Notification {
+                    tid,
+                    data,
+                    file_table,
+                    nofile_limit,
+                    status: NotificationStatus::Pending,
+                },
+            );
+            id
+        };
+        self.pollee.notify(IoEvents::IN);
+
+        let mut res = self
+            .wait_queue
+            .pause_until(|| self.check_response(id, self.is_wait_killable));
+        if let Ok(None) = res {
+            res = self
+                .wait_queue
+                .pause_until_killable(|| self.check_response(id, false));
+        }
+
+        // The notification is withdrawn if the wait fails.
+        self.state.lock().notifications.remove(&id);
+        self.pollee.invalidate();
+
+        match res {
+            Ok(Some(response)) => Ok(response),
+            Ok(None) => unreachable!(),
+            Err(err) if err.error() == Errno::EINTR => Err(Error::new(Errno::ERESTARTSYS)),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Checks whether the notification has been responded to.
+    ///
+    /// If `stops_at_received` is true, this method also returns `Some(Ok(None))` once the
+    /// notification is received.
+    fn check_response(
+        &self,
+        id: u64,
+        stops_at_received: bool,
+    ) -> Option<Result<Option<UserNotifResponse>>> {
+        let state = self.state.lock();
+        let notification = state.notifications.get(&id).unwrap();
+
+        match notification.status {
+            NotificationStatus::Replied(response) => Some(Ok(Some(response))),
+            _ if state.is_listener_closed => Some(Err(Error::with_message(
+                Errno::ENOSYS,
+                "the seccomp listener is closed",
+            ))),
+            NotificationStatus::Received if stops_at_received => Some(Ok(None)),
+            _ => None,
+        }
+    }
+
+    /// Marks that the filter is dropped.
+    ///
+    /// The supervisor sees the `HUP` event once all the threads that use the filter are gone.
+    pub(super) fn hang_up(&self) {
+        self.is_hung_up.store(true, Ordering::Relaxed);
+        self.pollee.notify(IoEvents::HUP);
+    }
+
+    fn try_recv(&self) -> Result<CSeccompNotif> {
+        let mut state = self.state.lock();
+
+        let Some((id, notification)) = state
+            .notifications
+            .iter_mut()
+            .find(|(_, notification)| matches!(notification.status, NotificationStatus::Pending))
+        else {
+            if self.is_hung_up.load(Ordering::Relaxed) {
+                return_errno_with_message!(Errno::ENOENT, "the seccomp filter is dropped");
+            }
+            return_errno_with_message!(Errno::EAGAIN, "there are no seccomp notifications");
+        };
+
+        notification.status = NotificationStatus::Received;
+        let notif = CSeccompNotif {
+            id: *id,
+            pid: notification.tid,
+            flags: 0,
+            data: notification.data,
+        };
+        drop(state);
+
+        self.pollee.notify(IoEvents::OUT);
+        self.wait_queue.wake_all();
+
+        Ok(notif)
+    }
+
+    /// Puts the notification back if it cannot be passed to the supervisor.
+    fn unreceive(&self, id: u64) {
+        let mut state = self.state.lock();
+        if let Some(notification) = state.notifications.get_mut(&id)
+            && matches!(notification.status, NotificationStatus::Received)
+        {
+            notification.status = NotificationStatus::Pending;
+        }
+        drop(state);
+
+        self.pollee.notify(IoEvents::IN);
+    }
+
+    fn respond(&self, id: u64, response: UserNotifResponse) -> Result<()> {
+        let mut state = self.state.lock();
+        let notification = get_received_notification(&mut state, id)?;
+        notification.status = NotificationStatus::Replied(response);
+        drop(state);
+
+        self.pollee.invalidate();
+        self.wait_queue.wake_all();
+
+        Ok(())
+    }
+
+    fn is_id_valid(&self, id: u64) -> bool {
+        self.state
+            .lock()
+            .notifications
+            .get(&id)
+            .is_some_and(|notification| matches!(notification.status, NotificationStatus::Received))
+    }
+
+    /// Installs the file into the file table of the thread, and responds with the file
+    /// descriptor if `responds` is true.
+    fn add_fd(
+        &self,
+        id: u64,
+        file: Arc<dyn FileLike>,
+        new_fd: Option<FileDesc>,
+        fd_flags: FdFlags,
+        responds: bool,
+    ) -> Result<FileDesc> {
+        let mut state = self.state.lock();
+        let notification = get_received_notification(&mut state, id)?;
+
+        let (fd, replaced_file) = {
+            let mut file_table = notification.file_table.write();
+            match new_fd {
+                Some(fd) => {
+                    if fd as usize >= notification.nofile_limit {
+                        return_errno_with_message!(
+                            Errno::EBADF,
+                            "the file descriptor exceeds the `RLIMIT_NOFILE` limit"
+                        );
+                    }
+                    (fd, file_table.insert_at(fd, file, fd_flags))
+                }
+                None => (file_table.insert(file, fd_flags)?, None),
+            }
+        };
+
+        if responds {
+            notification.status = NotificationStatus::Replied(UserNotifResponse::Return(fd as _));
+        }
+        drop(state);
+        drop(replaced_file);
+
+        if responds {
+            self.pollee.invalidate();
+            self.wait_queue.wake_all();
+        }
+
+        Ok(fd)
+    }
+
+    fn close_listener(&self) {
+        self.state.lock().is_listener_closed = true;
+        self.wait_queue.wake_all();
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        let state = self.state.lock();
+
+        let mut events = IoEvents::empty();
+        for notification in state.notifications.values() {
+            match notification.status {
+                NotificationStatus::Pending => events |= IoEvents::IN,
+                NotificationStatus::Received => events |= IoEvents::OUT,
+                NotificationStatus::Replied(_) => {}
+            }
+        }
+        if self.is_hung_up.load(Ordering::Relaxed) {
+            events |= IoEvents::HUP;
+        }
+
+        events
+    }
+}
+
+impl Debug for SeccompNotifier {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SeccompNotifier")
+            .field("is_wait_killable", &self.is_wait_killable)
+            .field("is_hung_up", &self.is_hung_up)
+            .finish_non_exhaustive()
+    }
+}
+
+fn get_received_notification(state: &mut NotifierState, id: u64) -> Result<&mut Notification> {
+    let Some(notification) = state.notifications.get_mut(&id) else {
+        return_errno_with_message!(Errno::ENOENT, "the seccomp notification does not exist");
+    };
+    if !matches!(notification.status, NotificationStatus::Received) {
+        return_errno_with_message!(
+            Errno::EINPROGRESS,
+            "the seccomp notification is not received or has been responded to"
+        );
+    }
+    Ok(notification)
+}
+
+/// The listener file of a filter, from which the supervisor receives the notifications.
+pub struct SeccompListener(Arc<SeccompNotifier>);
+
+impl SeccompListener {
+    fn recv(&self, arg: Vaddr) -> Result<()> {
+        let user_space = current_userspace!();
+
+        // The buffer must be zeroed, so that it can be extended in the future.
+        let buf = user_space.read_val::<CSeccompNotif>(arg)?;
+        if buf.as_bytes().iter().any(|byte| *byte != 0) {
+            return_errno_with_message!(Errno::EINVAL, "the notification buffer is not zeroed");
+        }
+
+        let notif = self.wait_events(IoEvents::IN, None, || self.0.try_recv())?;
+        if let Err(err) = user_space.write_val(arg, &notif) {
+            self.0.unreceive(notif.id);
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    fn send(&self, arg: Vaddr) -> Result<()> {
+        let resp = current_userspace!().read_val::<CSeccompNotifResp>(arg)?;
+
+        let response = match resp.flags {
+            0 if resp.error != 0 => UserNotifResponse::Return(resp.error as isize),
+            0 => UserNotifResponse::Return(resp.val as isize),
+            SECCOMP_USER_NOTIF_FLAG_CONTINUE if resp.error == 0 && resp.val == 0 => {
+                UserNotifResponse::Continue
+            }
+            _ => return_errno_with_message!(Errno::EINVAL, "the seccomp response is invalid"),
+        };
+
+        self.0.respond(resp.id, response)
+    }
+
+    fn add_fd(&self, arg: Vaddr) -> Result<FileDesc> {
+        let addfd = current_userspace!().read_val::<CSeccompNotifAddfd>(arg)?;
+
+        let Some(flags) = AddfdFlags::from_bits(addfd.flags) else {
+            return_errno_with_message!(Errno::EINVAL, "the addfd flags are invalid");
+        };
+        let fd_flags = if addfd.newfd_flags == 0 {
+            FdFlags::empty()
+        } else if addfd.newfd_flags == CreationFlags::O_CLOEXEC.bits() {
+            FdFlags::CLOEXEC
+        } else {
+            return_errno_with_message!(Errno::EINVAL, "the new file flags are invalid");
+        };
+        let new_fd = if flags.contains(AddfdFlags::SETFD) {
+            let Ok(new_fd) = FileDesc::try_from(addfd.newfd) else {
+                return_errno_with_message!(Errno::EBADF, "the file descriptor is invalid");
+            };
+            Some(new_fd)
+        } else if addfd.newfd != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the new file descriptor is not zero");
+        } else {
+            None
+        };
+
+        let file = {
+            let Ok(src_fd) = FileDesc::try_from(addfd.srcfd) else {
+                return_errno_with_message!(Errno::EBADF, "the file descriptor is invalid");
+            };
+            let current = Task::current().unwrap();
+            let file_table = current.as_thread_local().unwrap().borrow_file_table();
+            let file_table_locked = file_table.unwrap().read();
+            file_table_locked.get_file(src_fd)?.clone()
+        };
+
+        self.0.add_fd(
+            addfd.id,
+            file,
+            new_fd,
+            fd_flags,
+            flags.contains(AddfdFlags::SEND),
+        )
+    }
+}
+
+impl Pollable for SeccompListener {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.0
+            .pollee
+            .poll_with(mask, poller, || self.0.check_io_events())
+    }
+}
+
+impl FileLike for SeccompListener {
+    fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
+        match cmd {
+            IoctlCmd::SECCOMPNOTIFRECV => self.recv(arg)?,
+            IoctlCmd::SECCOMPNOTIFSEND => self.send(arg)?,
+            IoctlCmd::SECCOMPNOTIFIDVALID => {
+                let id = current_userspace!().read_val::<u64>(arg)?;
+                if !self.0.is_id_valid(id) {
+                    return_errno_with_message!(
+                        Errno::ENOENT,
+                        "the seccomp notification is no longer valid"
+                    );
+                }
+            }
+            IoctlCmd::SECCOMPNOTIFADDFD => return self.add_fd(arg),
+            _ => return_errno_with_message!(
+                Errno::EINVAL,
+                "the ioctl command is not supported by seccomp listeners"
+            ),
+        }
+
+        Ok(0)
+    }
+
+    fn metadata(&self) -> Metadata {
+        // This is a dummy implementation.
+        // TODO: Add "anonymous inode fs" and link the listener files to it.
+        Metadata::new_file(
+            0,
+            InodeMode::from_bits_truncate(0o600),
+            aster_block::BLOCK_SIZE,
+        )
+    }
+}
+
+impl Drop for SeccompListener {
+    fn drop(&mut self) {
+        self.0.close_listener();
+    }
+}
+
+/// The sizes of the structures used by the listener files (`struct seccomp_notif_sizes` in
+/// Linux).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct CSeccompNotifSizes {
+    pub seccomp_notif: u16,
+    pub seccomp_notif_resp: u16,
+    pub seccomp_data: u16,
+}
+
+impl CSeccompNotifSizes {
+    pub const fn new() -> Self {
+        Self {
+            seccomp_notif: size_of::<CSeccompNotif>() as u16,
+            seccomp_notif_resp: size_of::<CSeccompNotifResp>() as u16,
+            seccomp_data: size_of::<SeccompData>() as u16,
+        }
+    }
+}
+
+/// A notification received by the supervisor (`struct seccomp_notif` in Linux).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CSeccompNotif {
+    id: u64,
+    pid: u32,
+    flags: u32,
+    data: SeccompData,
+}
+
+/// A response sent by the supervisor (`struct seccomp_notif_resp` in Linux).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CSeccompNotifResp {
+    id: u64,
+    val: i64,
+    error: i32,
+    flags: u32,
+}
+
+/// A request to install a file (`struct seccomp_notif_addfd` in Linux).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CSeccompNotifAddfd {
+    id: u64,
+    flags: u32,
+    srcfd: u32,
+    newfd: u32,
+    newfd_flags: u32,
+}
+
+const SECCOMP_USER_NOTIF_FLAG_CONTINUE: u32 = 1 << 0;
+
+bitflags! {
+    struct AddfdFlags: u32 {
+        /// Installs the file at the specified file descriptor.
+        const SETFD = 1 << 0;
+        /// Responds with the file descriptor atomically.
+        const SEND = 1 << 1;
+    }
+}
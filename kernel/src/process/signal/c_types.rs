@@ -9,7 +9,7 @@ use aster_util::read_union_field;
 
 use super::sig_num::SigNum;
 use crate::{
-    arch::cpu::GpRegs,
+    arch::signal::SigContext,
     prelude::*,
     process::{Pid, Uid},
 };
@@ -57,6 +57,51 @@ impl siginfo_t {
     pub fn si_addr(&self) -> Vaddr {
         read_union_field!(self, Self, siginfo_fields.sigfault.addr)
     }
+
+    /// Sets the PID and the UID of the sender.
+    pub fn set_si_pid_uid(&mut self, pid: Pid, uid: Uid) {
+        self.siginfo_fields.common.first.piduid = siginfo_piduid_t { pid, uid };
+    }
+
+    pub fn si_pid(&self) -> Pid {
+        read_union_field!(self, Self, siginfo_fields.common.first.piduid.pid)
+    }
+
+    pub fn si_uid(&self) -> Uid {
+        read_union_field!(self, Self, siginfo_fields.common.first.piduid.uid)
+    }
+
+    /// Sets the fields of `SIGSYS`, which describe the system call that causes the signal.
+    pub fn set_si_sigsys(&mut self, call_addr: Vaddr, syscall: i32, arch: u32) {
+        self.siginfo_fields.sigsys = siginfo_sigsys_t {
+            call_addr,
+            syscall,
+            arch,
+        };
+    }
+
+    /// Sets the status of the child process for `SIGCHLD`.
+    pub fn set_si_status(&mut self, status: i32) {
+        self.siginfo_fields.common.second.sigchild = siginfo_sigchild_t { status };
+    }
+
+    pub fn set_si_value(&mut self, value: sigval_t) {
+        self.siginfo_fields.common.second.value = value;
+    }
+
+    pub fn si_value(&self) -> sigval_t {
+        read_union_field!(self, Self, siginfo_fields.common.second.value)
+    }
+}
+
+impl Debug for siginfo_t {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("siginfo_t")
+            .field("si_signo", &self.si_signo)
+            .field("si_errno", &self.si_errno)
+            .field("si_code", &self.si_code)
+            .finish_non_exhaustive()
+    }
 }
 
 #[derive(Clone, Copy, Pod)]
@@ -65,6 +110,7 @@ union siginfo_fields_t {
     bytes: [u8; 128 - mem::size_of::<i32>() * 4],
     common: siginfo_common_t,
     sigfault: siginfo_sigfault_t,
+    sigsys: siginfo_sigsys_t,
 }
 
 impl siginfo_fields_t {
@@ -143,6 +189,14 @@ struct siginfo_sigfault_t {
     first: siginfo_sigfault_first_t,
 }
 
+#[derive(Clone, Copy, Pod)]
+#[repr(C)]
+struct siginfo_sigsys_t {
+    call_addr: Vaddr, //*const c_void
+    syscall: i32,
+    arch: u32,
+}
+
 #[derive(Clone, Copy, Pod)]
 #[repr(C)]
 union siginfo_sigfault_first_t {
@@ -157,7 +211,12 @@ union siginfo_addr_bnd_t {
     upper: Vaddr, // *const c_void,
 }
 
-#[derive(Clone, Copy, Debug, Pod)]
+/// The user context saved in the signal frame.
+///
+/// This is the same as `struct ucontext` in Linux (rather than `ucontext_t` in glibc), which
+/// does not contain the FPU state. The FPU state is saved separately and is pointed to by
+/// `uc_mcontext`.
+#[derive(Clone, Copy, Debug, Pod, Default)]
 #[repr(C)]
 pub struct ucontext_t {
     pub uc_flags: u64,
@@ -165,20 +224,6 @@ pub struct ucontext_t {
     pub uc_stack: stack_t,
     pub uc_mcontext: mcontext_t,
     pub uc_sigmask: sigset_t,
-    pub fpregs: [u8; 64 * 8], //fxsave structure
-}
-
-impl Default for ucontext_t {
-    fn default() -> Self {
-        Self {
-            uc_flags: Default::default(),
-            uc_link: Default::default(),
-            uc_stack: Default::default(),
-            uc_mcontext: Default::default(),
-            uc_sigmask: Default::default(),
-            fpregs: [0u8; 64 * 8],
-        }
-    }
 }
 
 pub type stack_t = sigaltstack_t;
@@ -191,24 +236,7 @@ pub struct sigaltstack_t {
     pub ss_size: usize,
 }
 
-#[derive(Debug, Clone, Copy, Pod, Default)]
-#[repr(C)]
-pub struct mcontext_t {
-    pub inner: SignalCpuContext,
-    // TODO: the fields should be csgsfs, err, trapno, oldmask, and cr2
-    _unused0: [u64; 5],
-    // TODO: this field should be `fpregs: fpregset_t,`
-    _unused1: usize,
-    _reserved: [u64; 8],
-}
-
-#[derive(Debug, Clone, Copy, Pod, Default)]
-#[repr(C)]
-pub struct SignalCpuContext {
-    pub gp_regs: GpRegs,
-    pub fpregs_on_heap: u64,
-    pub fpregs: Vaddr, // *mut FpRegs,
-}
+pub type mcontext_t = SigContext;
 
 #[derive(Clone, Copy, Pod)]
 #[repr(C)]
@@ -60,6 +60,10 @@ define_std_signums! {
     SIGSYS    = 31, // Bad system call (SVr4); see also seccomp(2)
 }
 
+/// The first and the last real-time signals
+pub const SIGRTMIN: SigNum = SigNum::from_u8(MIN_RT_SIG_NUM);
+pub const SIGRTMAX: SigNum = SigNum::from_u8(MAX_RT_SIG_NUM);
+
 pub const SI_ASYNCNL: i32 = -60;
 pub const SI_TKILL: i32 = -6;
 pub const SI_SIGIO: i32 = -5;
@@ -99,6 +103,8 @@ pub const BUS_OBJERR: i32 = 3;
 pub const BUS_MCEERR_AR: i32 = 4;
 pub const BUS_MCEERR_AO: i32 = 5;
 
+pub const SYS_SECCOMP: i32 = 1;
+
 pub const CLD_EXITED: i32 = 1;
 pub const CLD_KILLED: i32 = 2;
 pub const CLD_DUMPED: i32 = 3;
@@ -21,14 +21,15 @@ use constants::SIGSEGV;
 pub use events::{SigEvents, SigEventsFilter};
 use ostd::{cpu::context::UserContext, user::UserContextApi};
 pub use pause::{with_sigmask_changed, Pause};
-pub use poll::{PollAdaptor, PollHandle, Pollable, Pollee, Poller};
+pub use poll::{do_io_nowait, PollAdaptor, PollHandle, Pollable, Pollee, Poller};
 use sig_action::{SigAction, SigActionFlags, SigDefaultAction};
 use sig_mask::SigMask;
 use sig_num::SigNum;
 pub use sig_stack::{SigStack, SigStackFlags};
 
-use super::posix_thread::ThreadLocal;
+use super::{posix_thread::ThreadLocal, ptrace, status::JobStatus, wait::notify_job_status};
 use crate::{
+    arch::signal::save_fpu_state_to_user,
     cpu::LinuxAbi,
     current_userspace,
     prelude::*,
@@ -58,6 +59,10 @@ pub fn handle_pending_signal(
         None
     };
 
+    // A tracee interrupted by the tracer enters the `PTRACE_EVENT_STOP` stop before handling
+    // the signals.
+    ptrace::stop_at_interrupt(ctx, user_ctx);
+
     let posix_thread = ctx.posix_thread;
     let current = ctx.process;
 
@@ -66,9 +71,21 @@ pub fn handle_pending_signal(
         if let Some(signal) = posix_thread.dequeue_signal(&sig_mask) {
             signal
         } else {
+            if let Some(syscall_number) = syscall_restart {
+                restart_syscall(user_ctx, syscall_number);
+            }
             return;
         }
     };
+
+    // A traced thread enters the signal-delivery-stop, where the tracer may suppress the signal
+    // or replace it with another one.
+    let Some(signal) = ptrace::stop_at_signal(ctx, user_ctx, signal) else {
+        if let Some(syscall_number) = syscall_restart {
+            restart_syscall(user_ctx, syscall_number);
+        }
+        return;
+    };
     let sig_num = signal.num();
     trace!("sig_num = {:?}, sig_name = {}", sig_num, sig_num.sig_name());
 
@@ -80,6 +97,9 @@ pub fn handle_pending_signal(
     match sig_action {
         SigAction::Ign => {
             trace!("Ignore signal {:?}", sig_num);
+            if let Some(syscall_number) = syscall_restart {
+                restart_syscall(user_ctx, syscall_number);
+            }
         }
         SigAction::User {
             handler_addr,
@@ -90,14 +110,7 @@ pub fn handle_pending_signal(
             if let Some(syscall_number) = syscall_restart
                 && flags.contains(SigActionFlags::SA_RESTART)
             {
-                #[cfg(target_arch = "x86_64")]
-                const SYSCALL_INSTR_LEN: usize = 2; // syscall
-                #[cfg(target_arch = "riscv64")]
-                const SYSCALL_INSTR_LEN: usize = 4; // ecall
-
-                user_ctx.set_syscall_num(syscall_number);
-                user_ctx
-                    .set_instruction_pointer(user_ctx.instruction_pointer() - SYSCALL_INSTR_LEN);
+                restart_syscall(user_ctx, syscall_number);
             }
 
             if flags.contains(SigActionFlags::SA_RESETHAND) {
@@ -141,16 +154,38 @@ pub fn handle_pending_signal(
                 }
                 SigDefaultAction::Ign => {}
                 SigDefaultAction::Stop => {
-                    let _ = ctx.thread.stop();
+                    if ctx.thread.stop().is_ok() {
+                        notify_job_status(ctx.process, JobStatus::Stopped(sig_num));
+                    }
                 }
                 SigDefaultAction::Cont => {
-                    let _ = ctx.thread.resume();
+                    if ctx.thread.resume().is_some() {
+                        notify_job_status(ctx.process, JobStatus::Continued);
+                    }
                 }
             }
+
+            // No user handler is invoked, so the interrupted system call is restarted
+            // transparently. For example, a background read from the terminal is restarted
+            // after being stopped by `SIGTTIN` and then continued.
+            if let Some(syscall_number) = syscall_restart {
+                restart_syscall(user_ctx, syscall_number);
+            }
         }
     }
 }
 
+/// Restarts the system call by rewinding the instruction pointer to the system call instruction.
+fn restart_syscall(user_ctx: &mut UserContext, syscall_number: usize) {
+    #[cfg(target_arch = "x86_64")]
+    const SYSCALL_INSTR_LEN: usize = 2; // syscall
+    #[cfg(target_arch = "riscv64")]
+    const SYSCALL_INSTR_LEN: usize = 4; // ecall
+
+    user_ctx.set_syscall_num(syscall_number);
+    user_ctx.set_instruction_pointer(user_ctx.instruction_pointer() - SYSCALL_INSTR_LEN);
+}
+
 #[expect(clippy::too_many_arguments)]
 pub fn handle_user_signal(
     ctx: &Context,
@@ -195,29 +230,30 @@ pub fn handle_user_signal(
 
     let user_space = ctx.user_space();
 
-    // 1. Write siginfo_t
+    // The signal mask is restored from the frame by `rt_sigreturn`, so that the handler can
+    // change it before returning.
+    let mut ucontext = ucontext_t {
+        uc_sigmask: old_mask.into(),
+        ..Default::default()
+    };
+
+    // 1. Write the FPU state.
+    stack_pointer = save_fpu_state_to_user(user_ctx, &mut ucontext, stack_pointer)?;
+
+    // 2. Write siginfo_t
     stack_pointer -= mem::size_of::<siginfo_t>() as u64;
     user_space.write_val(stack_pointer as _, &sig_info)?;
     let siginfo_addr = stack_pointer;
 
-    // 2. Write ucontext_t.
+    // 3. Write ucontext_t.
     stack_pointer = alloc_aligned_in_user_stack(stack_pointer, mem::size_of::<ucontext_t>(), 16)?;
-    let mut ucontext = ucontext_t {
-        uc_sigmask: mask.into(),
-        ..Default::default()
-    };
-    ucontext
-        .uc_mcontext
-        .inner
-        .gp_regs
-        .copy_from_raw(user_ctx.general_regs());
+    ucontext.uc_mcontext.copy_from_context(user_ctx);
     let sig_context = ctx.thread_local.sig_context().get();
     if let Some(sig_context_addr) = sig_context {
         ucontext.uc_link = sig_context_addr;
     } else {
         ucontext.uc_link = 0;
     }
-    // TODO: store fp regs in ucontext
     user_space.write_val(stack_pointer as _, &ucontext)?;
     let ucontext_addr = stack_pointer;
     // Store the ucontext addr in sig context of current thread.
@@ -225,7 +261,7 @@ pub fn handle_user_signal(
         .sig_context()
         .set(Some(ucontext_addr as Vaddr));
 
-    // 3. Write the address of the restorer code.
+    // 4. Write the address of the restorer code.
     if flags.contains(SigActionFlags::SA_RESTORER) {
         // If the SA_RESTORER flag is present, the restorer code address is provided by the user.
         stack_pointer = write_u64_to_user_stack(stack_pointer, restorer_addr as u64)?;
@@ -235,7 +271,7 @@ pub fn handle_user_signal(
         );
     }
 
-    // 4. Set correct register values
+    // 5. Set correct register values
     user_ctx.set_instruction_pointer(handler_addr as _);
     user_ctx.set_stack_pointer(stack_pointer as usize);
     // Parameters of signal handler
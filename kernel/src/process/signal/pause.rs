@@ -4,7 +4,10 @@ use core::sync::atomic::Ordering;
 
 use ostd::sync::{WaitQueue, Waiter};
 
-use super::sig_mask::SigMask;
+use super::{
+    constants::{SIGKILL, SIGSTOP},
+    sig_mask::SigMask,
+};
 use crate::{
     prelude::*,
     process::posix_thread::AsPosixThread,
@@ -97,6 +100,24 @@ pub trait Pause: WaitTimeout {
     /// [`EINTR`]: crate::error::Errno::EINTR
     #[track_caller]
     fn pause_timeout<'a>(&self, timeout: &TimeoutExt<'a>) -> Result<()>;
+
+    /// Pauses until the condition is met or the current thread is killed.
+    ///
+    /// Unlike [`Self::pause_until`], this method is not interrupted by ordinary signals. It
+    /// returns early only if the current thread is killed (see [`PosixThread::is_killed`]), so
+    /// the killed thread will not get stuck in a wait that would never end otherwise.
+    ///
+    /// # Errors
+    ///
+    /// This method will return an error with [`EINTR`] if the current thread is killed before the
+    /// condition is met.
+    ///
+    /// [`PosixThread::is_killed`]: crate::process::posix_thread::PosixThread::is_killed
+    /// [`EINTR`]: crate::error::Errno::EINTR
+    #[track_caller]
+    fn pause_until_killable<F, R>(&self, cond: F) -> Result<R>
+    where
+        F: FnMut() -> Option<R>;
 }
 
 impl Pause for Waiter {
@@ -136,6 +157,38 @@ impl Pause for Waiter {
         res
     }
 
+    fn pause_until_killable<F, R>(&self, cond: F) -> Result<R>
+    where
+        F: FnMut() -> Option<R>,
+    {
+        // No fast paths for `Waiter`. If the caller wants a fast path, it should do so _before_
+        // the waiter is created.
+
+        let Some(posix_thread) = self
+            .task()
+            .as_thread()
+            .and_then(|thread| thread.as_posix_thread())
+        else {
+            return self.wait_until_or_timeout_cancelled(cond, || Ok(()), None);
+        };
+
+        let cancel_cond = || {
+            if posix_thread.is_killed() {
+                return Err(Error::with_message(
+                    Errno::EINTR,
+                    "the current thread is killed",
+                ));
+            }
+            Ok(())
+        };
+
+        posix_thread.set_killable_waker(self.waker());
+        let res = self.wait_until_or_timeout_cancelled(cond, cancel_cond, None);
+        posix_thread.clear_signalled_waker();
+
+        res
+    }
+
     fn pause_timeout<'a>(&self, timeout: &TimeoutExt<'a>) -> Result<()> {
         let timer = timeout.check_expired()?.map(|timeout| {
             let waker = self.waker();
@@ -151,7 +204,11 @@ impl Pause for Waiter {
 
         if let Some(posix_thread) = posix_thread_opt {
             posix_thread.set_signalled_waker(self.waker());
-            self.wait();
+            // Signals that arrive before the signalled waker is set will not wake us up, so we
+            // must check them before going to sleep.
+            if !posix_thread.has_pending() {
+                self.wait();
+            }
             posix_thread.clear_signalled_waker();
         } else {
             self.wait();
@@ -204,6 +261,23 @@ impl Pause for WaitQueue {
     fn pause_timeout<'a>(&self, _timeout: &TimeoutExt<'a>) -> Result<()> {
         panic!("`pause_timeout` can only be used on `Waiter`");
     }
+
+    fn pause_until_killable<F, R>(&self, mut cond: F) -> Result<R>
+    where
+        F: FnMut() -> Option<R>,
+    {
+        // Fast path:
+        if let Some(res) = cond() {
+            return Ok(res);
+        }
+
+        let (waiter, _) = Waiter::new_pair();
+        let cond = || {
+            self.enqueue(waiter.waker());
+            cond()
+        };
+        waiter.pause_until_killable(cond)
+    }
 }
 
 /// Executes a closure after temporarily adjusting the signal mask of the current POSIX thread.
@@ -214,9 +288,13 @@ pub fn with_sigmask_changed<R>(
 ) -> R {
     let sig_mask = ctx.posix_thread.sig_mask();
 
-    // Save the original signal mask and apply the mask updates.
+    // Save the original signal mask and apply the mask updates. Like `rt_sigprocmask`, this
+    // should never block `SIGKILL` or `SIGSTOP`.
     let old_mask = sig_mask.load(Ordering::Relaxed);
-    sig_mask.store(mask_op(old_mask), Ordering::Relaxed);
+    let mut new_mask = mask_op(old_mask);
+    new_mask -= SIGKILL;
+    new_mask -= SIGSTOP;
+    sig_mask.store(new_mask, Ordering::Relaxed);
 
     // Perform the operation.
     let res = operate();
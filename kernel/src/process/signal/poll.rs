@@ -13,7 +13,7 @@ use ostd::{
 use crate::{
     events::{IoEvents, Observer, Subject},
     prelude::*,
-    time::wait::TimeoutExt,
+    time::wait::{ManagedTimeout, TimeoutExt},
 };
 
 /// A pollee represents any I/O object (e.g., a file or socket) that can be polled.
@@ -157,13 +157,23 @@ impl Pollee {
     /// the same poller. Unlike [`Self::poll_with`], this method performs poller registration
     /// without checking (and perhaps caching) the current events.
     pub fn register_poller(&self, poller: &mut PollHandle, mask: IoEvents) {
-        self.inner
-            .subject
-            .register_observer(poller.observer.clone(), mask);
+        let subject = &self.inner.subject;
+        if poller.is_exclusive {
+            subject.register_exclusive_observer(poller.observer.clone(), mask);
+        } else {
+            subject.register_observer(poller.observer.clone(), mask);
+        }
 
         poller.pollees.push(Arc::downgrade(&self.inner));
     }
 
+    /// Returns whether there are any registered pollers.
+    ///
+    /// The result may be stale if pollers are registered or unregistered concurrently.
+    pub fn has_pollers(&self) -> bool {
+        self.inner.subject.has_observers()
+    }
+
     /// Notifies pollers of some events.
     ///
     /// This method invalidates the (internal) cached events and wakes up all registered pollers
@@ -201,6 +211,8 @@ pub struct PollHandle {
     observer: Weak<dyn Observer<IoEvents>>,
     // The associated pollees.
     pollees: Vec<Weak<PolleeInner>>,
+    // Whether the observer is registered as an exclusive observer.
+    is_exclusive: bool,
 }
 
 impl PollHandle {
@@ -213,6 +225,20 @@ impl PollHandle {
         Self {
             observer,
             pollees: Vec::new(),
+            is_exclusive: false,
+        }
+    }
+
+    /// Constructs a new handle with the observer, which will be registered as an exclusive
+    /// observer.
+    ///
+    /// See [`Poller::new_exclusive`] for the semantics of exclusive observers. The same note about
+    /// logic errors in [`Self::new`] also applies here.
+    pub fn new_exclusive(observer: Weak<dyn Observer<IoEvents>>) -> Self {
+        Self {
+            observer,
+            pollees: Vec::new(),
+            is_exclusive: true,
         }
     }
 
@@ -283,13 +309,15 @@ impl Poller {
     /// Constructs a new poller to wait for interesting events.
     ///
     /// If `timeout` is specified, [`Self::wait`] will fail with [`ETIME`] after the specified
-    /// timeout is expired.
+    /// timeout is expired. The timeout is a high-resolution one (see
+    /// [`ManagedTimeout::new_high_res`]), so it is not rounded to whole jiffies.
     ///
     /// [`ETIME`]: crate::error::Errno::ETIME
     pub fn new(timeout: Option<&Duration>) -> Self {
         let (waiter, waker) = Waiter::new_pair();
 
-        let mut timeout_ext = TimeoutExt::from(timeout);
+        let mut timeout_ext =
+            TimeoutExt::from(timeout.map(|timeout| ManagedTimeout::new_high_res(*timeout)));
         timeout_ext.freeze();
 
         Self {
@@ -299,6 +327,17 @@ impl Poller {
         }
     }
 
+    /// Constructs a new poller to wait exclusively for interesting events.
+    ///
+    /// When an event occurs, only one of the exclusive pollers waiting for it is woken up, while
+    /// all the non-exclusive pollers are woken up. This is useful if the event can be consumed by
+    /// only one waiter, e.g., an incoming connection of a listening socket.
+    pub fn new_exclusive(timeout: Option<&Duration>) -> Self {
+        let mut poller = Self::new(timeout);
+        poller.poller.is_exclusive = true;
+        poller
+    }
+
     /// Returns a mutable reference of [`PollHandle`].
     pub fn as_handle_mut(&mut self) -> &mut PollHandle {
         &mut self.poller
@@ -319,6 +358,12 @@ impl Observer<IoEvents> for Waker {
     fn on_events(&self, _events: &IoEvents) {
         self.wake_up();
     }
+
+    fn on_exclusive_events(&self, _events: &IoEvents) -> bool {
+        // A waiter that is not sleeping will check the events by itself, so try to pass the events
+        // to a sleeping waiter.
+        self.wake_up_if_sleeping()
+    }
 }
 
 /// The `Pollable` trait allows for waiting for events and performing event-based operations.
@@ -345,16 +390,47 @@ pub trait Pollable {
     /// for some interesting events specified in `mask` to happen and try again.
     ///
     /// This method will fail with `ETIME` if the timeout is specified and the event does not occur
-    /// before the timeout expires.
+    /// before the timeout expires. It will fail with `EAGAIN` without waiting if the current
+    /// thread is performing a non-blocking I/O operation (e.g., with `RWF_NOWAIT`).
     ///
     /// The user must ensure that a call to `try_op()` does not fail with `EAGAIN` when the
     /// interesting events occur. However, it is allowed to have spurious `EAGAIN` failures due to
     /// race opitions where the events are consumed by another thread.
     #[track_caller]
-    fn wait_events<F, R>(
+    fn wait_events<F, R>(&self, mask: IoEvents, timeout: Option<&Duration>, try_op: F) -> Result<R>
+    where
+        Self: Sized,
+        F: FnMut() -> Result<R>,
+    {
+        self.wait_events_impl(mask, timeout, false, try_op)
+    }
+
+    /// Waits exclusively for events and performs event-based operations.
+    ///
+    /// This method is the same as [`Pollable::wait_events`], except that the waiting thread is
+    /// not woken up together with other exclusive waiters for the same event. See
+    /// [`Poller::new_exclusive`] for details.
+    #[track_caller]
+    fn wait_events_exclusive<F, R>(
         &self,
         mask: IoEvents,
         timeout: Option<&Duration>,
+        try_op: F,
+    ) -> Result<R>
+    where
+        Self: Sized,
+        F: FnMut() -> Result<R>,
+    {
+        self.wait_events_impl(mask, timeout, true, try_op)
+    }
+
+    #[doc(hidden)]
+    #[track_caller]
+    fn wait_events_impl<F, R>(
+        &self,
+        mask: IoEvents,
+        timeout: Option<&Duration>,
+        is_exclusive: bool,
         mut try_op: F,
     ) -> Result<R>
     where
@@ -367,13 +443,22 @@ pub trait Pollable {
             result => return result,
         }
 
+        // Fast path: Return immediately if the current I/O operation must not block.
+        if is_current_io_nowait() {
+            return_errno_with_message!(Errno::EAGAIN, "the operation would block");
+        }
+
         // Fast path: Return immediately if the timeout is zero.
         if timeout.is_some_and(|duration| duration.is_zero()) {
             return_errno_with_message!(Errno::ETIME, "the timeout expired");
         }
 
         // Create the poller and register to wait for the events.
-        let mut poller = Poller::new(timeout);
+        let mut poller = if is_exclusive {
+            Poller::new_exclusive(timeout)
+        } else {
+            Poller::new(timeout)
+        };
         if self.poll(mask, Some(poller.as_handle_mut())).is_empty() {
             poller.wait()?;
         }
@@ -391,6 +476,33 @@ pub trait Pollable {
     }
 }
 
+/// Returns whether the current thread is performing an I/O operation that must not block.
+fn is_current_io_nowait() -> bool {
+    let Some(task) = Task::current() else {
+        return false;
+    };
+    task.as_thread_local()
+        .is_some_and(|thread_local| thread_local.is_io_nowait().get())
+}
+
+/// Performs the I/O operation, which fails with `EAGAIN` instead of blocking.
+///
+/// This is used for the files opened with `O_NONBLOCK` that block with
+/// [`Pollable::wait_events`].
+pub fn do_io_nowait<R>(op: impl FnOnce() -> Result<R>) -> Result<R> {
+    let Some(task) = Task::current() else {
+        return op();
+    };
+    let Some(thread_local) = task.as_thread_local() else {
+        return op();
+    };
+
+    let was_nowait = thread_local.is_io_nowait().replace(true);
+    let res = op();
+    thread_local.is_io_nowait().set(was_nowait);
+    res
+}
+
 #[cfg(ktest)]
 mod test {
     use ostd::prelude::*;
@@ -108,7 +108,7 @@ impl SigActionFlags {
     }
 
     pub fn contains_unsupported_flag(&self) -> bool {
-        self.intersects(SigActionFlags::SA_NOCLDSTOP | SigActionFlags::SA_NOCLDWAIT)
+        self.intersects(SigActionFlags::SA_NOCLDWAIT)
     }
 }
 
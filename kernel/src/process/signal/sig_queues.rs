@@ -204,7 +204,11 @@ impl Queues {
             signal
                 .as_ref()
                 .is_some_and(|signal| !blocked.contains(signal.num()))
-        }) || self.rt_queues.iter().any(|rt_queue| !rt_queue.is_empty())
+        }) || self.rt_queues.iter().any(|rt_queue| {
+            rt_queue
+                .front()
+                .is_some_and(|signal| !blocked.contains(signal.num()))
+        })
     }
 
     fn get_std_queue_mut(&mut self, signum: SigNum) -> &mut Option<Box<dyn Signal>> {
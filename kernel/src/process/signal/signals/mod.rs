@@ -2,6 +2,8 @@
 
 pub mod fault;
 pub mod kernel;
+pub mod seccomp;
+pub mod timer;
 pub mod user;
 
 use core::{any::Any, fmt::Debug};
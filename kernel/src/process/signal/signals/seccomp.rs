@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::Signal;
+use crate::{
+    prelude::*,
+    process::signal::{
+        c_types::siginfo_t,
+        constants::{SIGSYS, SYS_SECCOMP},
+        sig_num::SigNum,
+    },
+};
+
+/// A `SIGSYS` signal sent when a seccomp filter traps a system call.
+///
+/// The signal carries the information of the system call, together with the data returned by
+/// the filter as `si_errno`.
+#[derive(Debug, Clone, Copy)]
+pub struct SeccompSignal {
+    call_addr: Vaddr,
+    syscall: i32,
+    arch: u32,
+    errno: u16,
+}
+
+impl SeccompSignal {
+    pub fn new(call_addr: Vaddr, syscall: i32, arch: u32, errno: u16) -> Self {
+        Self {
+            call_addr,
+            syscall,
+            arch,
+            errno,
+        }
+    }
+}
+
+impl Signal for SeccompSignal {
+    fn num(&self) -> SigNum {
+        SIGSYS
+    }
+
+    fn to_info(&self) -> siginfo_t {
+        let mut info = siginfo_t::new(SIGSYS, SYS_SECCOMP);
+        info.si_errno = self.errno as i32;
+        info.set_si_sigsys(self.call_addr, self.syscall, self.arch);
+        info
+    }
+}
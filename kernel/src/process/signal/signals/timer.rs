@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::fmt::Debug;
+
+use super::Signal;
+use crate::process::signal::{
+    c_types::{siginfo_t, sigval_t},
+    constants::SI_TIMER,
+    sig_num::SigNum,
+};
+
+/// A signal sent when a POSIX timer expires.
+///
+/// The signal carries the `sigev_value` given to `timer_create`, which is used by, e.g., glibc
+/// to find the notification function of the timer.
+#[derive(Clone, Copy)]
+pub struct TimerSignal {
+    num: SigNum,
+    value: sigval_t,
+}
+
+impl TimerSignal {
+    pub fn new(num: SigNum, value: sigval_t) -> Self {
+        Self { num, value }
+    }
+}
+
+impl Debug for TimerSignal {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TimerSignal")
+            .field("num", &self.num)
+            .field("value", &self.value.read_ptr())
+            .finish()
+    }
+}
+
+impl Signal for TimerSignal {
+    fn num(&self) -> SigNum {
+        self.num
+    }
+
+    fn to_info(&self) -> siginfo_t {
+        let mut info = siginfo_t::new(self.num, SI_TIMER);
+        info.set_si_value(self.value);
+        info
+    }
+}
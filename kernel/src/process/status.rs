@@ -4,7 +4,8 @@
 
 use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 
-use super::ExitCode;
+use super::{signal::sig_num::SigNum, ExitCode};
+use crate::prelude::*;
 
 /// The status of a process.
 ///
@@ -12,12 +13,23 @@ use super::ExitCode;
 /// 1. Whether the process is a zombie (i.e., all its threads have exited);
 /// 2. Whether the process is the vfork child, which shares the user-space virtual memory
 ///    with its parent process;
-/// 3. The exit code of the process.
+/// 3. The exit code of the process;
+/// 4. The job-control status of the process that has not been waited for by its parent.
 #[derive(Debug)]
 pub struct ProcessStatus {
     is_zombie: AtomicBool,
     is_vfork_child: AtomicBool,
     exit_code: AtomicU32,
+    job_status: SpinLock<Option<JobStatus>>,
+}
+
+/// The job-control status of a process, i.e., whether it is stopped or continued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    /// The process is stopped by the signal.
+    Stopped(SigNum),
+    /// The process is continued by `SIGCONT`.
+    Continued,
 }
 
 impl Default for ProcessStatus {
@@ -26,6 +38,7 @@ impl Default for ProcessStatus {
             is_zombie: AtomicBool::new(false),
             is_vfork_child: AtomicBool::new(false),
             exit_code: AtomicU32::new(0),
+            job_status: SpinLock::new(None),
         }
     }
 }
@@ -71,3 +84,27 @@ impl ProcessStatus {
         self.exit_code.store(exit_code, Ordering::Relaxed);
     }
 }
+
+impl ProcessStatus {
+    /// Sets the job-control status, which replaces the one that has not been waited for.
+    pub(super) fn set_job_status(&self, job_status: JobStatus) {
+        *self.job_status.lock() = Some(job_status);
+    }
+
+    /// Waits for the job-control status if it is wanted by `is_wanted`.
+    ///
+    /// The job-control status is cleared unless `keep` is true, so that it is reported only
+    /// once.
+    pub(super) fn wait_job_status(
+        &self,
+        is_wanted: impl FnOnce(JobStatus) -> bool,
+        keep: bool,
+    ) -> Option<JobStatus> {
+        let mut job_status = self.job_status.lock();
+        let status = job_status.filter(|status| is_wanted(*status))?;
+        if !keep {
+            *job_status = None;
+        }
+        Some(status)
+    }
+}
@@ -4,7 +4,17 @@
 
 use super::{
     process_filter::ProcessFilter,
-    signal::{constants::SIGCHLD, with_sigmask_changed},
+    signal::{
+        constants::{
+            CLD_CONTINUED, CLD_DUMPED, CLD_EXITED, CLD_KILLED, CLD_STOPPED, CLD_TRAPPED, SIGCHLD,
+            SIGCONT,
+        },
+        sig_action::{SigAction, SigActionFlags},
+        sig_num::SigNum,
+        signals::kernel::KernelSignal,
+        with_sigmask_changed,
+    },
+    status::JobStatus,
     ExitCode, Pid, Process,
 };
 use crate::{
@@ -19,68 +29,178 @@ use crate::{
 bitflags! {
     pub struct WaitOptions: u32 {
         const WNOHANG = 0x1;
-        //Note: Below flags are not supported yet
         const WSTOPPED = 0x2; // Same as WUNTRACED
         const WEXITED = 0x4;
         const WCONTINUED = 0x8;
         const WNOWAIT = 0x01000000;
+        // Note: Below flags are accepted but ignored. They only matter for the children that
+        // do not send `SIGCHLD` on exit, which are not distinguished yet.
         const WNOTHREAD = 0x20000000;
         const WALL = 0x40000000;
         const WCLONE = 0x80000000;
     }
 }
 
-impl WaitOptions {
-    pub fn supported(&self) -> bool {
-        let unsupported_flags = WaitOptions::all() - WaitOptions::WNOHANG;
-        !self.intersects(unsupported_flags)
+/// The status of a child process that has been waited for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitStatus {
+    /// The child process has exited with the exit code.
+    Exited(ExitCode),
+    /// The child process has been stopped by the signal.
+    Stopped(SigNum),
+    /// The child process has been continued by `SIGCONT`.
+    Continued,
+    /// The tracee has entered a ptrace-stop with the stop status.
+    ///
+    /// The stop status is the signal number, possibly with `0x80` for system call stops or
+    /// with the ptrace event in bits 8..16.
+    Traced(u32),
+}
+
+impl WaitStatus {
+    /// Returns as a 32-bit integer encoded as specified in wait(2) man page.
+    pub fn as_u32(&self) -> u32 {
+        match self {
+            // The exit code has already been encoded.
+            Self::Exited(exit_code) => *exit_code,
+            Self::Stopped(signum) => ((signum.as_u8() as u32) << 8) | 0x7f,
+            Self::Continued => 0xffff,
+            Self::Traced(status) => (status << 8) | 0x7f,
+        }
+    }
+
+    /// Returns the `si_code` and the `si_status` of `SIGCHLD` as specified in waitid(2) man page.
+    pub fn as_code_and_status(&self) -> (i32, i32) {
+        match self {
+            Self::Exited(exit_code) => {
+                let signum = (exit_code & 0x7f) as i32;
+                if signum == 0 {
+                    (CLD_EXITED, ((exit_code >> 8) & 0xff) as i32)
+                } else if exit_code & 0x80 != 0 {
+                    (CLD_DUMPED, signum)
+                } else {
+                    (CLD_KILLED, signum)
+                }
+            }
+            Self::Stopped(signum) => (CLD_STOPPED, signum.as_u8() as i32),
+            Self::Continued => (CLD_CONTINUED, SIGCONT.as_u8() as i32),
+            Self::Traced(status) => (CLD_TRAPPED, *status as i32),
+        }
+    }
+}
+
+impl From<JobStatus> for WaitStatus {
+    fn from(job_status: JobStatus) -> Self {
+        match job_status {
+            JobStatus::Stopped(signum) => Self::Stopped(signum),
+            JobStatus::Continued => Self::Continued,
+        }
     }
 }
 
+/// Waits for a child process to change its status.
+///
+/// The exited children are waited for if `WEXITED` is specified, and the stopped (or continued)
+/// children are waited for if `WSTOPPED` (or `WCONTINUED`) is specified. The ptrace-stops of the
+/// tracees are always waited for, where the tracees are treated as children. The status change
+/// is reported only once unless `WNOWAIT` is specified, in which case the exited child is not
+/// reaped either.
 pub fn wait_child_exit(
     child_filter: ProcessFilter,
     wait_options: WaitOptions,
     ctx: &Context,
-) -> Result<Option<Arc<Process>>> {
+) -> Result<Option<(Arc<Process>, WaitStatus)>> {
     let current = ctx.process;
-    let zombie_child = with_sigmask_changed(
+    let is_nowait = wait_options.contains(WaitOptions::WNOWAIT);
+    let waited_child = with_sigmask_changed(
         ctx,
         |sigmask| sigmask + SIGCHLD,
         || {
             current.children_wait_queue().pause_until(|| {
+                let is_wanted = |child: &Process| match child_filter {
+                    ProcessFilter::Any => true,
+                    ProcessFilter::WithPid(pid) => child.pid() == pid,
+                    ProcessFilter::WithPgid(pgid) => child.pgid() == pgid,
+                };
                 let unwaited_children = current
                     .children()
                     .lock()
                     .values()
-                    .filter(|child| match child_filter {
-                        ProcessFilter::Any => true,
-                        ProcessFilter::WithPid(pid) => child.pid() == pid,
-                        ProcessFilter::WithPgid(pgid) => child.pgid() == pgid,
-                    })
+                    .filter(|child| is_wanted(child))
                     .cloned()
                     .collect::<Vec<_>>();
+                let tracees = current
+                    .tracees()
+                    .lock()
+                    .values()
+                    .map(|thread| thread.as_posix_thread().unwrap().process())
+                    .filter(|tracee| is_wanted(tracee))
+                    .collect::<Vec<_>>();
 
-                if unwaited_children.is_empty() {
+                // Only the main threads are traced, so the ptrace state of the main thread is
+                // checked for each tracee process.
+                let traced_child = tracees.iter().find_map(|tracee| {
+                    let main_thread = tracee.main_thread();
+                    let status = main_thread
+                        .as_posix_thread()
+                        .unwrap()
+                        .tracee()
+                        .wait_stop_status(is_nowait)?;
+                    Some((tracee.clone(), WaitStatus::Traced(status)))
+                });
+                if let Some(traced_child) = traced_child {
+                    return Some(Ok(Some(traced_child)));
+                }
+
+                if unwaited_children.is_empty() && tracees.is_empty() {
                     return Some(Err(Error::with_message(
                         Errno::ECHILD,
                         "the process has no child to wait",
                     )));
                 }
 
+                if unwaited_children.is_empty() {
+                    return if wait_options.contains(WaitOptions::WNOHANG) {
+                        Some(Ok(None))
+                    } else {
+                        None
+                    };
+                }
+
                 // return immediately if we find a zombie child
                 let zombie_child = unwaited_children
                     .iter()
                     .find(|child| child.status().is_zombie());
 
-                if let Some(zombie_child) = zombie_child {
-                    let zombie_pid = zombie_child.pid();
-                    if wait_options.contains(WaitOptions::WNOWAIT) {
-                        // does not reap child, directly return
-                        return Some(Ok(Some(zombie_child.clone())));
-                    } else {
-                        reap_zombie_child(current, zombie_pid);
-                        return Some(Ok(Some(zombie_child.clone())));
+                if let Some(zombie_child) = zombie_child
+                    && wait_options.contains(WaitOptions::WEXITED)
+                {
+                    let status = WaitStatus::Exited(zombie_child.status().exit_code());
+                    if !is_nowait {
+                        reap_zombie_child(current, zombie_child.pid());
                     }
+                    return Some(Ok(Some((zombie_child.clone(), status))));
+                }
+
+                let job_child = unwaited_children
+                    .iter()
+                    .filter(|child| !child.status().is_zombie())
+                    .find_map(|child| {
+                        let job_status = child.status().wait_job_status(
+                            |job_status| match job_status {
+                                JobStatus::Stopped(_) => {
+                                    wait_options.contains(WaitOptions::WSTOPPED)
+                                }
+                                JobStatus::Continued => {
+                                    wait_options.contains(WaitOptions::WCONTINUED)
+                                }
+                            },
+                            is_nowait,
+                        )?;
+                        Some((child.clone(), WaitStatus::from(job_status)))
+                    });
+                if let Some(job_child) = job_child {
+                    return Some(Ok(Some(job_child)));
                 }
 
                 if wait_options.contains(WaitOptions::WNOHANG) {
@@ -93,7 +213,32 @@ pub fn wait_child_exit(
         },
     )??;
 
-    Ok(zombie_child)
+    Ok(waited_child)
+}
+
+/// Notifies the parent that the job-control status of the process has changed.
+pub(super) fn notify_job_status(process: &Process, job_status: JobStatus) {
+    process.status().set_job_status(job_status);
+
+    let Some(parent) = process.parent().lock().process().upgrade() else {
+        return;
+    };
+    notify_child_stopped(&parent);
+}
+
+/// Notifies the parent (or the tracer) that a child (or a tracee) has stopped or continued.
+///
+/// The parent receives `SIGCHLD` unless it has set `SA_NOCLDSTOP` for `SIGCHLD`, and wakes up
+/// if it is waiting for the children.
+pub(super) fn notify_child_stopped(parent: &Process) {
+    let is_nocldstop = matches!(
+        parent.sig_dispositions().lock().get(SIGCHLD),
+        SigAction::User { flags, .. } if flags.contains(SigActionFlags::SA_NOCLDSTOP)
+    );
+    if !is_nocldstop {
+        parent.enqueue_signal(KernelSignal::new(SIGCHLD));
+    }
+    parent.children_wait_queue().wake_all();
 }
 
 /// Free zombie child with pid, returns the exit code of child process.
@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A sampling profiler of the kernel.
+//!
+//! The profiler is enabled with the `profiler.freq=<HZ>` kernel command-line argument, which
+//! specifies how many times per second each CPU is sampled. At the timer interrupts, the stack
+//! of the interrupted context is sampled by OSTD (see [`StackSample`]). The samples are kept in
+//! a fixed-size buffer first, since no memory can be allocated in the interrupt context, and
+//! then the identical stack traces are counted in a work item.
+//!
+//! When the system exits, the counted stack traces are dumped to the console, one per line:
+//! ```text
+//! [profile] cpu=<CPU> count=<COUNT> pcs=<PC>,<PC>,...
+//! ```
+//! The program counters are in hexadecimal, starting from the interrupted instruction. The
+//! user stacks are not walked, so all the samples in the user mode are counted as `pcs=user`.
+//! The lines are parsed and symbolized by `cargo osdk profile --sampler`.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use ostd::{
+    arch::timer::{register_sample_callback, StackSample, MAX_SAMPLE_DEPTH, TIMER_FREQ},
+    cpu_local,
+    sync::LocalIrqDisabled,
+};
+use spin::Once;
+
+use crate::{
+    prelude::*,
+    thread::work_queue::{submit_work_item, work_item::WorkItem, WorkPriority},
+};
+
+crate::kernel_param! {
+    /// The number of the samples of each CPU per second. The profiler is disabled if it is 0.
+    static FREQ: u64 = ("profiler.freq", 0);
+}
+
+/// The maximum number of the samples that are not counted yet.
+const MAX_PENDING_SAMPLES: usize = 512;
+
+/// The number of the timer ticks between two samples on a CPU.
+static TICKS_PER_SAMPLE: AtomicU32 = AtomicU32::new(1);
+
+cpu_local! {
+    /// The number of the timer ticks until the next sample on the CPU.
+    static TICKS_TO_SAMPLE: AtomicU32 = AtomicU32::new(1);
+}
+
+static PENDING_SAMPLES: SpinLock<PendingSamples, LocalIrqDisabled> =
+    SpinLock::new(PendingSamples::new());
+
+/// The work item that counts the pending samples.
+static COUNT_WORK: Once<Arc<WorkItem>> = Once::new();
+
+/// The number of the samples of each stack trace.
+static STACK_COUNTS: Mutex<BTreeMap<StackKey, u64>> = Mutex::new(BTreeMap::new());
+
+/// The identity of a stack trace, i.e., the CPU and the program counters.
+///
+/// The program counters are empty for the user mode.
+type StackKey = (u32, Vec<usize>);
+
+pub(super) fn init() {
+    let freq = FREQ.get();
+    if freq == 0 {
+        return;
+    }
+
+    let ticks_per_sample = (TIMER_FREQ / freq).clamp(1, u32::MAX as u64) as u32;
+    TICKS_PER_SAMPLE.store(ticks_per_sample, Ordering::Relaxed);
+    COUNT_WORK.call_once(|| WorkItem::new(Box::new(count_pending_samples)));
+    register_sample_callback(on_sample);
+
+    println!(
+        "[kernel] The profiler samples each CPU every {} timer ticks",
+        ticks_per_sample
+    );
+}
+
+fn on_sample(sample: &StackSample) {
+    let ticks_to_sample = TICKS_TO_SAMPLE.get_on_cpu(sample.cpu());
+    if ticks_to_sample.fetch_sub(1, Ordering::Relaxed) > 1 {
+        return;
+    }
+    ticks_to_sample.store(TICKS_PER_SAMPLE.load(Ordering::Relaxed), Ordering::Relaxed);
+
+    let mut pending = PENDING_SAMPLES.lock();
+    pending.push(sample);
+    if pending.len == MAX_PENDING_SAMPLES / 2 {
+        submit_work_item(COUNT_WORK.get().unwrap().clone(), WorkPriority::Normal);
+    }
+}
+
+fn count_pending_samples() {
+    let mut stack_counts = STACK_COUNTS.lock();
+
+    loop {
+        // Move the samples out in batches, so that the local IRQs are not disabled for long.
+        let mut batch = Vec::new();
+        {
+            let mut pending = PENDING_SAMPLES.lock();
+            while batch.len() < 64 {
+                let Some(sample) = pending.pop() else {
+                    break;
+                };
+                batch.push(sample);
+            }
+        }
+        if batch.is_empty() {
+            break;
+        }
+
+        for sample in batch {
+            let pcs = if sample.is_user {
+                Vec::new()
+            } else {
+                sample.frames[..sample.len as usize].to_vec()
+            };
+            *stack_counts.entry((sample.cpu, pcs)).or_insert(0) += 1;
+        }
+    }
+}
+
+/// Dumps the counted stack traces to the console.
+///
+/// The stack traces are cleared after they are dumped, so calling this function again only
+/// dumps the samples collected after the last call.
+pub(super) fn dump() {
+    if COUNT_WORK.get().is_none() {
+        return;
+    }
+
+    count_pending_samples();
+    let stack_counts = core::mem::take(&mut *STACK_COUNTS.lock());
+    let nr_dropped = core::mem::take(&mut PENDING_SAMPLES.lock().nr_dropped);
+
+    for ((cpu, pcs), count) in stack_counts.iter() {
+        let pcs = if pcs.is_empty() {
+            "user".to_string()
+        } else {
+            pcs.iter()
+                .map(|pc| format!("{:#x}", pc))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        println!("[profile] cpu={} count={} pcs={}", cpu, count, pcs);
+    }
+    if nr_dropped > 0 {
+        println!("[kernel] The profiler dropped {} samples", nr_dropped);
+    }
+}
+
+/// A copy of a [`StackSample`] that waits to be counted.
+#[derive(Clone, Copy)]
+struct PendingSample {
+    cpu: u32,
+    is_user: bool,
+    len: u32,
+    frames: [usize; MAX_SAMPLE_DEPTH],
+}
+
+impl PendingSample {
+    const EMPTY: Self = Self {
+        cpu: 0,
+        is_user: false,
+        len: 0,
+        frames: [0; MAX_SAMPLE_DEPTH],
+    };
+}
+
+/// A stack of the samples that wait to be counted.
+struct PendingSamples {
+    samples: [PendingSample; MAX_PENDING_SAMPLES],
+    len: usize,
+    /// The number of the samples that are dropped because the buffer is full.
+    nr_dropped: u64,
+}
+
+impl PendingSamples {
+    const fn new() -> Self {
+        Self {
+            samples: [PendingSample::EMPTY; MAX_PENDING_SAMPLES],
+            len: 0,
+            nr_dropped: 0,
+        }
+    }
+
+    fn push(&mut self, sample: &StackSample) {
+        if self.len == MAX_PENDING_SAMPLES {
+            self.nr_dropped += 1;
+            return;
+        }
+
+        let frames = sample.frames();
+        let pending = &mut self.samples[self.len];
+        pending.cpu = sample.cpu().as_usize() as u32;
+        pending.is_user = sample.is_user();
+        pending.len = frames.len() as u32;
+        pending.frames[..frames.len()].copy_from_slice(frames);
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<PendingSample> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        Some(self.samples[self.len])
+    }
+}
@@ -0,0 +1,216 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The idle loop of CPUs.
+//!
+//! When a CPU has nothing to run, its idle thread halts the CPU until the next interrupt, so
+//! that an idle guest does not consume the CPU time of the host. On x86-64, the CPU is halted
+//! with `MWAIT` if it is supported, or with `HLT` otherwise.
+//!
+//! The time that each CPU spends halted is measured with the TSC. The time that a CPU is busy is
+//! sampled at each timer interrupt as either the user time or the system time. The time spent
+//! in the interrupt handlers is measured by OSTD. These statistics are reported in `/proc/stat`
+//! and `/proc/uptime`.
+//!
+//! The periodic timer interrupts still wake up the idle CPUs, except for the `nohz_full=` CPUs
+//! (see the [`tick`] module). To support tickless idle on the other CPUs, the timer can be
+//! stopped and restarted by the [`IdleHooks`] that run around the halts.
+//!
+//! [`tick`]: super::tick
+
+use core::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+
+use ostd::{
+    arch::{read_tsc, timer::TIMER_FREQ, trap::is_kernel_interrupted},
+    cpu::{CpuId, PinCurrentCpu},
+    cpu_local,
+    task::disable_preempt,
+    timer, trap,
+};
+use spin::Once;
+
+use super::sched_class::time::clocks_to_ns;
+
+/// The method to halt an idle CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IdleMethod {
+    /// Halts with `HLT` on x86-64 or `WFI` on RISC-V.
+    Halt,
+    /// Halts with `MONITOR` and `MWAIT` on x86-64.
+    #[cfg(target_arch = "x86_64")]
+    Mwait,
+}
+
+/// The `MWAIT` hint that selects the C-1 state.
+///
+/// The C-1 state has the shortest wakeup latency, the same as `HLT`.
+#[cfg(target_arch = "x86_64")]
+const MWAIT_HINT_C1: u32 = 0;
+
+static IDLE_METHOD: Once<IdleMethod> = Once::new();
+
+/// The hooks that run when a CPU enters and exits the idle state.
+///
+/// The hooks run in the idle thread of the CPU with the preemption enabled, but the idle
+/// thread never migrates to other CPUs.
+pub trait IdleHooks: Sync + Send {
+    /// Runs before the CPU is halted, e.g., to stop the periodic timer.
+    fn enter_idle(&self, cpu: CpuId);
+
+    /// Runs after the CPU is woken up, e.g., to restart the periodic timer.
+    fn exit_idle(&self, cpu: CpuId);
+}
+
+static IDLE_HOOKS: Once<&'static dyn IdleHooks> = Once::new();
+
+/// Registers the hooks that run when a CPU enters and exits the idle state.
+///
+/// Only the first registered hooks take effect.
+#[expect(dead_code)]
+pub fn register_idle_hooks(hooks: &'static dyn IdleHooks) {
+    IDLE_HOOKS.call_once(|| hooks);
+}
+
+/// The time accounting of a CPU.
+struct CpuStat {
+    /// The number of the timer ticks that interrupt the user mode.
+    user_ticks: AtomicU64,
+    /// The number of the timer ticks that interrupt the kernel mode.
+    system_ticks: AtomicU64,
+    /// The number of the TSC clocks when the CPU is halted.
+    idle_clocks: AtomicU64,
+    /// Whether the CPU is halted.
+    is_idle: AtomicBool,
+}
+
+cpu_local! {
+    static CPU_STATS: CpuStat = CpuStat {
+        user_ticks: AtomicU64::new(0),
+        system_ticks: AtomicU64::new(0),
+        idle_clocks: AtomicU64::new(0),
+        is_idle: AtomicBool::new(false),
+    };
+}
+
+/// The time that a CPU spends in each state since the system boots.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CpuTimes {
+    /// The time spent in the user mode.
+    pub user: Duration,
+    /// The time spent in the kernel mode.
+    pub system: Duration,
+    /// The time spent halted.
+    pub idle: Duration,
+    /// The time spent in the top halves of the interrupts.
+    pub irq: Duration,
+    /// The time spent in the bottom halves of the interrupts.
+    pub softirq: Duration,
+}
+
+/// Returns the time that the CPU spends in each state since the system boots.
+pub fn cpu_times(cpu: CpuId) -> CpuTimes {
+    let stat = CPU_STATS.get_on_cpu(cpu);
+    let ticks_to_duration = |ticks: u64| Duration::from_millis(ticks * 1000 / TIMER_FREQ);
+
+    CpuTimes {
+        user: ticks_to_duration(stat.user_ticks.load(Ordering::Relaxed)),
+        system: ticks_to_duration(stat.system_ticks.load(Ordering::Relaxed)),
+        idle: Duration::from_nanos(clocks_to_ns(stat.idle_clocks.load(Ordering::Relaxed))),
+        irq: Duration::from_nanos(clocks_to_ns(trap::top_half_clocks(cpu))),
+        softirq: Duration::from_nanos(clocks_to_ns(trap::bottom_half_clocks(cpu))),
+    }
+}
+
+pub(super) fn init() {
+    let method = IDLE_METHOD.call_once(|| {
+        #[cfg(target_arch = "x86_64")]
+        if ostd::cpu::has_mwait() {
+            return IdleMethod::Mwait;
+        }
+        IdleMethod::Halt
+    });
+    log::info!("Idle CPUs are halted with {:?}", method);
+
+    timer::register_callback(account_tick);
+}
+
+/// Starts the time accounting on the current application processor.
+///
+/// This function should be called once on each application processor.
+pub(super) fn init_on_ap() {
+    timer::register_callback(account_tick);
+}
+
+fn account_tick() {
+    let irq_guard = trap::disable_local();
+    let stat = CPU_STATS.get_on_cpu(irq_guard.current_cpu());
+
+    // The time when the CPU is halted is measured in `enter_idle`.
+    if stat.is_idle.load(Ordering::Relaxed) {
+        return;
+    }
+
+    if is_kernel_interrupted() {
+        stat.system_ticks.fetch_add(1, Ordering::Relaxed);
+    } else {
+        stat.user_ticks.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Accounts the timer ticks that are missed while the timer of the CPU is stopped as the user
+/// time.
+pub(super) fn account_user_ticks(cpu: CpuId, ticks: u64) {
+    CPU_STATS
+        .get_on_cpu(cpu)
+        .user_ticks
+        .fetch_add(ticks, Ordering::Relaxed);
+}
+
+/// Returns whether the CPU is halted.
+pub(super) fn is_idle(cpu: CpuId) -> bool {
+    CPU_STATS.get_on_cpu(cpu).is_idle.load(Ordering::Relaxed)
+}
+
+/// Halts the current CPU until the next interrupt.
+///
+/// This function should only be called by the idle thread of the current CPU when there is
+/// nothing else to run.
+pub fn enter_idle() {
+    let cpu = disable_preempt().current_cpu();
+    let stat = CPU_STATS.get_on_cpu(cpu);
+    let hooks = IDLE_HOOKS.get();
+
+    if let Some(hooks) = hooks {
+        hooks.enter_idle(cpu);
+    }
+
+    stat.is_idle.store(true, Ordering::Relaxed);
+    let start = read_tsc();
+    let start_interrupt = interrupt_clocks(cpu);
+    match IDLE_METHOD.get().unwrap() {
+        IdleMethod::Halt => ostd::cpu::sleep_for_interrupt(),
+        #[cfg(target_arch = "x86_64")]
+        IdleMethod::Mwait => ostd::cpu::sleep_for_interrupt_with_mwait(MWAIT_HINT_C1),
+    }
+    let end = read_tsc();
+    let end_interrupt = interrupt_clocks(cpu);
+    stat.is_idle.store(false, Ordering::Relaxed);
+
+    // The interrupts that wake up the CPU are handled before the halt returns, and their time is
+    // accounted separately.
+    let interrupt = end_interrupt.saturating_sub(start_interrupt);
+    stat.idle_clocks.fetch_add(
+        end.saturating_sub(start).saturating_sub(interrupt),
+        Ordering::Relaxed,
+    );
+
+    if let Some(hooks) = hooks {
+        hooks.exit_idle(cpu);
+    }
+}
+
+fn interrupt_clocks(cpu: CpuId) -> u64 {
+    trap::top_half_clocks(cpu) + trap::bottom_half_clocks(cpu)
+}
@@ -0,0 +1,44 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_block::ioprio::{self, IoPrioClass, IoPriority};
+
+use super::{Nice, SchedPolicy};
+use crate::{process::posix_thread::AsPosixThread, thread::Thread};
+
+pub(super) fn init() {
+    ioprio::register_current_priority_fn(current_io_priority);
+}
+
+/// Returns the I/O priority of the bios submitted by the current thread.
+///
+/// Like Linux, a POSIX thread without an I/O class set gets an I/O priority derived from its
+/// scheduling policy. Kernel threads (e.g., the writeback thread) always get the lowest
+/// best-effort priority, so that the background I/O does not delay the I/O of the user tasks.
+fn current_io_priority() -> IoPriority {
+    let Some(thread) = Thread::current() else {
+        return IoPriority::NONE;
+    };
+    let Some(posix_thread) = thread.as_posix_thread() else {
+        return IoPriority::new(IoPrioClass::BestEffort, IoPriority::NR_LEVELS - 1).unwrap();
+    };
+
+    let io_priority = posix_thread.io_priority();
+    if io_priority.class() != IoPrioClass::None {
+        return io_priority;
+    }
+
+    match thread.sched_attr().policy() {
+        SchedPolicy::Stop | SchedPolicy::RealTime { .. } => {
+            IoPriority::new(IoPrioClass::RealTime, nice_to_level(Nice::default())).unwrap()
+        }
+        SchedPolicy::Fair(nice) => {
+            IoPriority::new(IoPrioClass::BestEffort, nice_to_level(nice)).unwrap()
+        }
+        SchedPolicy::Idle => IoPriority::new(IoPrioClass::Idle, 0).unwrap(),
+    }
+}
+
+/// Maps a nice value in `-20..=19` to an I/O priority level in `0..=7`.
+fn nice_to_level(nice: Nice) -> u16 {
+    ((nice.value().get() + 20) / 5) as u16
+}
@@ -0,0 +1,179 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The isolation of the CPUs for latency-sensitive workloads.
+//!
+//! Like Linux, the CPUs are isolated with the kernel parameters:
+//! - `isolcpus=` excludes the CPUs from the placement of the new threads and from the load
+//!   balancing, so that only the threads whose CPU affinity is set to them run on them;
+//! - `nohz_full=` stops the timer interrupts of the CPUs while they run a single user thread
+//!   (see the [`tick`] module).
+//!
+//! The BSP cannot be a `nohz_full=` CPU, since its timer updates the jiffies. At least one CPU
+//! is left for the housekeeping work.
+//!
+//! Reference: <https://docs.kernel.org/admin-guide/kernel-parameters.html>
+//!
+//! [`tick`]: super::tick
+
+use ostd::cpu::{num_cpus, CpuId, CpuSet};
+use spin::Once;
+
+use crate::prelude::*;
+
+crate::kernel_param! {
+    /// Isolates the CPUs from the scheduler in the format of `[<flag>,...,]<cpu-list>`, e.g.,
+    /// `isolcpus=nohz,2-3`. The new threads are not placed on the isolated CPUs, and the threads
+    /// are not balanced to them, unless the CPU affinity of the threads contains only isolated
+    /// CPUs.
+    ///
+    /// The flag `nohz` also adds the CPUs to `nohz_full`. The flags `domain` and `managed_irq` are
+    /// accepted for compatibility with Linux.
+    static ISOLCPUS: &'static str = ("isolcpus", "");
+}
+
+crate::kernel_param! {
+    /// Stops the timer interrupts of the CPUs in the CPU list, e.g., `nohz_full=1,3-5`, while
+    /// they run a single user thread or nothing. The BSP (CPU 0) is ignored.
+    static NOHZ_FULL: &'static str = ("nohz_full", "");
+}
+
+static ISOLATED_CPUS: Once<CpuSet> = Once::new();
+static NOHZ_FULL_CPUS: Once<CpuSet> = Once::new();
+
+pub(super) fn init() {
+    let mut isolated = CpuSet::new_empty();
+    let mut nohz_full = CpuSet::new_empty();
+
+    let isolcpus = ISOLCPUS.get();
+    if !isolcpus.is_empty() {
+        match parse_isolcpus(isolcpus) {
+            Some((has_nohz, cpus)) => {
+                isolated = to_cpu_set(&cpus, "isolcpus");
+                if has_nohz {
+                    nohz_full = isolated.clone();
+                }
+            }
+            None => warn!("Invalid `isolcpus={}`, which is ignored", isolcpus),
+        }
+    }
+
+    let nohz_full_list = NOHZ_FULL.get();
+    if !nohz_full_list.is_empty() {
+        match parse_cpu_list(nohz_full_list) {
+            Some(cpus) => {
+                for cpu in to_cpu_set(&cpus, "nohz_full").iter() {
+                    nohz_full.add(cpu);
+                }
+            }
+            None => warn!("Invalid `nohz_full={}`, which is ignored", nohz_full_list),
+        }
+    }
+
+    if isolated.count() == num_cpus() {
+        warn!("All the CPUs are isolated, which is ignored");
+        isolated = CpuSet::new_empty();
+    }
+    if nohz_full.contains(CpuId::bsp()) {
+        warn!("The BSP cannot be a `nohz_full` CPU, which is ignored");
+        nohz_full.remove(CpuId::bsp());
+    }
+
+    if !isolated.is_empty() {
+        info!("Isolated CPUs: {:?}", cpu_ids(&isolated));
+    }
+    if !nohz_full.is_empty() {
+        info!("Dynticks-full CPUs: {:?}", cpu_ids(&nohz_full));
+    }
+
+    ISOLATED_CPUS.call_once(|| isolated);
+    NOHZ_FULL_CPUS.call_once(|| nohz_full);
+}
+
+/// Returns whether the CPU is isolated from the placement and the balancing of the threads.
+pub(super) fn is_isolated(cpu: CpuId) -> bool {
+    ISOLATED_CPUS.get().is_some_and(|cpus| cpus.contains(cpu))
+}
+
+/// Returns whether the timer interrupts of the CPU can be stopped while it runs a single user
+/// thread.
+pub fn is_nohz_full(cpu: CpuId) -> bool {
+    NOHZ_FULL_CPUS.get().is_some_and(|cpus| cpus.contains(cpu))
+}
+
+/// Parses the value of `isolcpus=` into whether the `nohz` flag is given and the CPU list.
+fn parse_isolcpus(value: &str) -> Option<(bool, Vec<usize>)> {
+    let mut has_nohz = false;
+    let mut list = value;
+    while let Some((flag, rest)) = list.split_once(',')
+        && flag.starts_with(|c: char| c.is_ascii_alphabetic())
+    {
+        match flag {
+            "nohz" => has_nohz = true,
+            "domain" | "managed_irq" => (),
+            _ => return None,
+        }
+        list = rest;
+    }
+
+    Some((has_nohz, parse_cpu_list(list)?))
+}
+
+/// Parses a CPU list like `0,2-4` into the sorted CPU IDs.
+fn parse_cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for range in list.split(',') {
+        let (first, last) = range.split_once('-').unwrap_or((range, range));
+        let first: usize = first.parse().ok()?;
+        let last: usize = last.parse().ok()?;
+        if first > last {
+            return None;
+        }
+        cpus.extend(first..=last);
+    }
+
+    cpus.sort_unstable();
+    cpus.dedup();
+    Some(cpus)
+}
+
+/// Converts the CPU IDs into a [`CpuSet`], ignoring the CPUs that do not exist.
+fn to_cpu_set(cpus: &[usize], param: &str) -> CpuSet {
+    let mut set = CpuSet::new_empty();
+    for &cpu in cpus {
+        match CpuId::try_from(cpu) {
+            Ok(cpu) => set.add(cpu),
+            Err(_) => warn!(
+                "CPU {} in `{}` does not exist, which is ignored",
+                cpu, param
+            ),
+        }
+    }
+    set
+}
+
+fn cpu_ids(cpus: &CpuSet) -> Vec<usize> {
+    cpus.iter().map(|cpu| cpu.as_usize()).collect()
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn parse_cpu_lists() {
+        assert_eq!(parse_cpu_list("3"), Some(vec![3]));
+        assert_eq!(parse_cpu_list("5,1-3,2"), Some(vec![1, 2, 3, 5]));
+        assert_eq!(parse_cpu_list(""), None);
+        assert_eq!(parse_cpu_list("3-1"), None);
+        assert_eq!(parse_cpu_list("1,,2"), None);
+
+        assert_eq!(parse_isolcpus("1-2"), Some((false, vec![1, 2])));
+        assert_eq!(
+            parse_isolcpus("nohz,domain,managed_irq,1,3"),
+            Some((true, vec![1, 3]))
+        );
+        assert_eq!(parse_isolcpus("unknown,1"), None);
+    }
+}
@@ -1,11 +1,37 @@
 // SPDX-License-Identifier: MPL-2.0
 
+mod cpu_idle;
+mod io_priority;
+mod isolation;
 mod nice;
+mod pi_mutex;
 mod sched_class;
 mod stats;
+mod tick;
 
 pub use self::{
+    cpu_idle::{cpu_times, enter_idle, CpuTimes},
+    isolation::is_nohz_full,
     nice::{AtomicNice, Nice},
-    sched_class::{init, RealTimePolicy, RealTimePriority, SchedAttr, SchedPolicy},
-    stats::{loadavg, nr_queued_and_running},
+    pi_mutex::{PiMutex, PiMutexGuard},
+    sched_class::{
+        CpuBandwidth, CpuBandwidthStat, RealTimePolicy, RealTimePriority, SchedAttr, SchedPolicy,
+    },
+    stats::{cpu_sched_stat, loadavg, nr_queued_and_running, CpuSchedStat, TaskSchedStat},
 };
+
+pub fn init() {
+    isolation::init();
+    tick::init();
+    sched_class::init();
+    cpu_idle::init();
+    io_priority::init();
+}
+
+/// Initializes the scheduling on the current application processor.
+///
+/// This function should be called once on each application processor.
+pub fn init_on_ap() {
+    cpu_idle::init_on_ap();
+    tick::init_on_ap();
+}
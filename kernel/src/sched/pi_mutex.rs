@@ -0,0 +1,196 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+use ostd::sync::{Mutex, MutexGuard, SpinLock};
+
+use super::SchedPolicy;
+use crate::thread::Thread;
+
+/// A mutex with priority inheritance (PI).
+///
+/// The mutex is like [`Mutex`], except that the thread holding the mutex inherits the REAL-TIME
+/// scheduling policies of the threads waiting for the mutex. So a low-priority thread cannot
+/// delay a high-priority thread for long by holding a mutex that the high-priority thread needs,
+/// even if there are medium-priority threads that keep running.
+///
+/// This is intended for the kernel-internal locks that are held briefly but taken by threads of
+/// all priorities. Note that the inheritance is not transitive: If the holding thread is itself
+/// waiting for another `PiMutex`, the holder of the other mutex does not inherit the policies.
+pub struct PiMutex<T: ?Sized> {
+    state: SpinLock<PiState>,
+    inner: Mutex<T>,
+}
+
+/// The state of priority inheritance.
+///
+/// The owner, if any, always inherits all of the waiter policies.
+struct PiState {
+    /// The thread holding the mutex, if the mutex is locked by a thread.
+    owner: Option<Arc<Thread>>,
+    /// The policies of the waiting threads.
+    waiter_policies: Vec<SchedPolicy>,
+}
+
+impl<T> PiMutex<T> {
+    /// Creates a new mutex.
+    pub const fn new(val: T) -> Self {
+        Self {
+            state: SpinLock::new(PiState {
+                owner: None,
+                waiter_policies: Vec::new(),
+            }),
+            inner: Mutex::new(val),
+        }
+    }
+}
+
+impl<T: ?Sized> PiMutex<T> {
+    /// Acquires the mutex.
+    ///
+    /// This method runs in a block way until the mutex can be acquired. While waiting, the
+    /// current thread lends its scheduling policy to the thread holding the mutex if the
+    /// policy is a REAL-TIME one.
+    #[track_caller]
+    pub fn lock(&self) -> PiMutexGuard<T> {
+        let current = Thread::current();
+        if let Some(guard) = self.inner.try_lock() {
+            return self.new_guard(guard, current, None);
+        }
+
+        let policy = current
+            .as_ref()
+            .map(|thread| thread.sched_attr().effective_policy())
+            .filter(|policy| matches!(policy, SchedPolicy::RealTime { .. }));
+        if let Some(policy) = policy {
+            let mut state = self.state.lock();
+            state.waiter_policies.push(policy);
+            if let Some(owner) = state.owner.as_ref() {
+                owner.sched_attr().inherit_policies(owner, &[policy]);
+            }
+        }
+
+        let guard = self.inner.lock();
+        self.new_guard(guard, current, policy)
+    }
+
+    /// Tries to acquire the mutex immediately.
+    pub fn try_lock(&self) -> Option<PiMutexGuard<T>> {
+        let guard = self.inner.try_lock()?;
+        Some(self.new_guard(guard, Thread::current(), None))
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// This method is zero-cost: By holding a mutable reference to the lock, the compiler has
+    /// already statically guaranteed that access to the data is exclusive.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.get_mut()
+    }
+
+    /// Makes the current thread the owner after it acquires the mutex.
+    ///
+    /// If the current thread has been waiting with the given policy, the policy is no longer
+    /// inherited.
+    fn new_guard<'a>(
+        &'a self,
+        guard: MutexGuard<'a, T>,
+        current: Option<Arc<Thread>>,
+        waiter_policy: Option<SchedPolicy>,
+    ) -> PiMutexGuard<'a, T> {
+        let mut state = self.state.lock();
+
+        if let Some(policy) = waiter_policy {
+            let waiter_policies = &mut state.waiter_policies;
+            let index = waiter_policies.iter().position(|p| *p == policy).unwrap();
+            waiter_policies.swap_remove(index);
+        }
+
+        if let Some(current) = current {
+            if !state.waiter_policies.is_empty() {
+                current
+                    .sched_attr()
+                    .inherit_policies(&current, &state.waiter_policies);
+            }
+            state.owner = Some(current);
+        }
+
+        PiMutexGuard { mutex: self, guard }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for PiMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, f)
+    }
+}
+
+/// A guard that provides exclusive access to the data protected by a [`PiMutex`].
+///
+/// The guard cannot be sent to other threads, because the mutex must be released by the thread
+/// that inherits the policies.
+#[clippy::has_significant_drop]
+#[must_use]
+pub struct PiMutexGuard<'a, T: ?Sized> {
+    mutex: &'a PiMutex<T>,
+    guard: MutexGuard<'a, T>,
+}
+
+impl<T: ?Sized> Deref for PiMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<T: ?Sized> DerefMut for PiMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+impl<T: ?Sized> Drop for PiMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        // The owner stops inheriting the policies before the inner mutex is released (i.e.,
+        // when `self.guard` is dropped after this method), so that the next owner will inherit
+        // the same policies.
+        let mut state = self.mutex.state.lock();
+        if let Some(owner) = state.owner.take() {
+            if !state.waiter_policies.is_empty() {
+                owner
+                    .sched_attr()
+                    .uninherit_policies(&state.waiter_policies);
+            }
+        }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for PiMutexGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn try_lock_does_not_unlock() {
+        let lock = PiMutex::new(0);
+
+        let mut guard = lock.lock();
+        *guard += 1;
+        assert!(lock.try_lock().is_none());
+        drop(guard);
+
+        assert_eq!(*lock.try_lock().unwrap(), 1);
+    }
+}
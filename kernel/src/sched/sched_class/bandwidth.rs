@@ -0,0 +1,154 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::sync::Arc;
+
+use ostd::{arch::read_tsc as sched_clock, sync::SpinLock};
+
+use super::time::{clocks_to_ns, ns_to_clocks};
+
+/// The CPU bandwidth of a group of threads.
+///
+/// The threads in the group can run for at most `quota` in each `period` (see the `cpu.max`
+/// file of cgroup v2). Once the quota is used up, the threads in the FAIR scheduling class are
+/// throttled until the next period begins. A `CpuBandwidth` may have a parent, in which case
+/// the quota of the parent also limits the threads in the group.
+#[derive(Debug)]
+pub struct CpuBandwidth {
+    parent: Option<Arc<CpuBandwidth>>,
+    state: SpinLock<BandwidthState>,
+}
+
+/// The statistics of a [`CpuBandwidth`].
+#[derive(Debug, Clone, Copy)]
+pub struct CpuBandwidthStat {
+    /// The total CPU time used by the threads, in microseconds.
+    pub usage_us: u64,
+    /// The number of periods in which the threads run.
+    pub nr_periods: u64,
+    /// The number of periods in which the threads are throttled.
+    pub nr_throttled: u64,
+}
+
+#[derive(Debug)]
+struct BandwidthState {
+    quota_us: Option<u64>,
+    period_us: u64,
+    /// The quota in TSC clock units.
+    quota: Option<u64>,
+    /// The period in TSC clock units.
+    period: u64,
+    period_start: u64,
+    /// The runtime used in the current period, in TSC clock units.
+    runtime: u64,
+    is_throttled: bool,
+    /// The total runtime, in TSC clock units.
+    usage: u64,
+    nr_periods: u64,
+    nr_throttled: u64,
+}
+
+impl CpuBandwidth {
+    /// The default period, in microseconds.
+    pub const DEFAULT_PERIOD_US: u64 = 100_000;
+
+    /// Creates a new CPU bandwidth without the quota.
+    pub fn new(parent: Option<Arc<CpuBandwidth>>) -> Arc<Self> {
+        let state = BandwidthState {
+            quota_us: None,
+            period_us: Self::DEFAULT_PERIOD_US,
+            quota: None,
+            period: ns_to_clocks(Self::DEFAULT_PERIOD_US * 1000),
+            period_start: sched_clock(),
+            runtime: 0,
+            is_throttled: false,
+            usage: 0,
+            nr_periods: 0,
+            nr_throttled: 0,
+        };
+
+        Arc::new(Self {
+            parent,
+            state: SpinLock::new(state),
+        })
+    }
+
+    /// Returns the quota and the period, in microseconds.
+    ///
+    /// A quota of `None` means that the bandwidth is unlimited.
+    pub fn max(&self) -> (Option<u64>, u64) {
+        let state = self.state.disable_irq().lock();
+        (state.quota_us, state.period_us)
+    }
+
+    /// Sets the quota and the period, in microseconds.
+    pub fn set_max(&self, quota_us: Option<u64>, period_us: u64) {
+        let mut state = self.state.disable_irq().lock();
+        state.quota_us = quota_us;
+        state.period_us = period_us;
+        state.quota = quota_us.map(|quota_us| ns_to_clocks(quota_us * 1000));
+        state.period = ns_to_clocks(period_us * 1000);
+    }
+
+    /// Returns the statistics.
+    pub fn stat(&self) -> CpuBandwidthStat {
+        let state = self.state.disable_irq().lock();
+        CpuBandwidthStat {
+            usage_us: clocks_to_ns(state.usage) / 1000,
+            nr_periods: state.nr_periods,
+            nr_throttled: state.nr_throttled,
+        }
+    }
+
+    /// Charges the runtime (in TSC clock units) and returns whether the threads are throttled.
+    pub(super) fn charge(&self, runtime: u64) -> bool {
+        let now = sched_clock();
+        let mut is_throttled = false;
+        for bandwidth in self.self_and_ancestors() {
+            let mut state = bandwidth.state.disable_irq().lock();
+            state.refresh(now);
+            state.usage += runtime;
+            state.runtime += runtime;
+            if state.is_exhausted() {
+                if !state.is_throttled {
+                    state.is_throttled = true;
+                    state.nr_throttled += 1;
+                }
+                is_throttled = true;
+            }
+        }
+        is_throttled
+    }
+
+    /// Returns whether the threads are throttled.
+    pub(super) fn is_throttled(&self) -> bool {
+        let now = sched_clock();
+        self.self_and_ancestors().any(|bandwidth| {
+            let mut state = bandwidth.state.disable_irq().lock();
+            state.refresh(now);
+            state.is_exhausted()
+        })
+    }
+
+    fn self_and_ancestors(&self) -> impl Iterator<Item = &CpuBandwidth> {
+        core::iter::successors(Some(self), |bandwidth| bandwidth.parent.as_deref())
+    }
+}
+
+impl BandwidthState {
+    /// Starts a new period if the current one has elapsed.
+    fn refresh(&mut self, now: u64) {
+        let elapsed = now.saturating_sub(self.period_start);
+        if elapsed < self.period {
+            return;
+        }
+
+        self.period_start += elapsed / self.period * self.period;
+        self.runtime = 0;
+        self.is_throttled = false;
+        self.nr_periods += 1;
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.quota.is_some_and(|quota| self.runtime >= quota)
+    }
+}
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::{collections::BinaryHeap, sync::Arc};
+use alloc::{collections::BinaryHeap, sync::Arc, vec::Vec};
 use core::{
     cmp::{self, Reverse},
     sync::atomic::{AtomicU64, Ordering::Relaxed},
@@ -8,6 +8,7 @@ use core::{
 
 use ostd::{
     cpu::{num_cpus, CpuId},
+    sync::SpinLock,
     task::{
         scheduler::{EnqueueFlags, UpdateFlags},
         Task,
@@ -15,6 +16,7 @@ use ostd::{
 };
 
 use super::{
+    bandwidth::CpuBandwidth,
     time::{base_slice_clocks, min_period_clocks},
     CurrentRuntime, SchedAttr, SchedClassRq,
 };
@@ -93,10 +95,16 @@ pub const fn nice_to_weight(nice: Nice) -> u64 {
 ///
 ///     period_delta > time_slice
 ///         || vruntime > rq_min_vruntime + normalized_time_slice
+///
+/// # CPU bandwidth
+///
+/// A thread may be limited by a [`CpuBandwidth`]. If the bandwidth is used up, the thread will
+/// be preempted and throttled until the next period of the bandwidth begins.
 #[derive(Debug)]
 pub struct FairAttr {
     weight: AtomicU64,
     vruntime: AtomicU64,
+    bandwidth: SpinLock<Option<Arc<CpuBandwidth>>>,
 }
 
 impl FairAttr {
@@ -104,6 +112,7 @@ impl FairAttr {
         FairAttr {
             weight: nice_to_weight(nice).into(),
             vruntime: Default::default(),
+            bandwidth: SpinLock::new(None),
         }
     }
 
@@ -111,6 +120,25 @@ impl FairAttr {
         self.weight.store(nice_to_weight(nice), Relaxed);
     }
 
+    pub fn set_bandwidth(&self, bandwidth: Option<Arc<CpuBandwidth>>) {
+        *self.bandwidth.disable_irq().lock() = bandwidth;
+    }
+
+    /// Charges the runtime to the CPU bandwidth and returns whether the thread is throttled.
+    fn charge_bandwidth(&self, delta: u64) -> bool {
+        let bandwidth = self.bandwidth.disable_irq().lock();
+        bandwidth
+            .as_ref()
+            .is_some_and(|bandwidth| bandwidth.charge(delta))
+    }
+
+    fn is_throttled(&self) -> bool {
+        let bandwidth = self.bandwidth.disable_irq().lock();
+        bandwidth
+            .as_ref()
+            .is_some_and(|bandwidth| bandwidth.is_throttled())
+    }
+
     fn update_vruntime(&self, delta: u64) -> (u64, u64) {
         let weight = self.weight.load(Relaxed);
         let delta = delta * WEIGHT_0 / weight;
@@ -165,10 +193,13 @@ impl Ord for FairQueueItem {
 /// ensure the efficiency for finding next-to-run threads.
 #[derive(Debug)]
 pub(super) struct FairClassRq {
-    #[expect(unused)]
     cpu: CpuId,
     /// The ready-to-run threads.
     entities: BinaryHeap<Reverse<FairQueueItem>>,
+    /// The ready-to-run threads whose CPU bandwidth is used up.
+    ///
+    /// They are moved back to `entities` when the next period of the bandwidth begins.
+    throttled: Vec<Arc<Task>>,
     /// The minimum of vruntime in the run queue. Serves as the initial
     /// value of newly-enqueued threads.
     min_vruntime: u64,
@@ -180,6 +211,7 @@ impl FairClassRq {
         Self {
             cpu,
             entities: BinaryHeap::new(),
+            throttled: Vec::new(),
             min_vruntime: 0,
             total_weight: 0,
         }
@@ -217,6 +249,81 @@ impl FairClassRq {
     fn time_slice(&self, cur_weight: u64) -> u64 {
         self.period() * cur_weight / (self.total_weight + cur_weight)
     }
+
+    /// Moves a ready-to-run thread that is allowed to run on this CPU from the busiest run queue.
+    ///
+    /// The thread with the greatest vruntime is chosen, since it would wait for the longest time
+    /// in the busiest run queue. Its lag behind the minimum vruntime is kept after moving, so that
+    /// it neither starves nor takes over this CPU.
+    pub fn pull_from(&mut self, busiest: &mut FairClassRq) -> Option<Arc<Task>> {
+        let index = busiest
+            .entities
+            .iter()
+            .enumerate()
+            .filter(|(_, Reverse(item))| {
+                let thread = item.0.as_thread().unwrap();
+                thread.atomic_cpu_affinity().contains(self.cpu, Relaxed)
+            })
+            .max_by_key(|(_, Reverse(item))| item.key())
+            .map(|(index, _)| index)?;
+
+        // The iteration order of a `BinaryHeap` is the order of its underlying vector.
+        let mut entities = core::mem::take(&mut busiest.entities).into_vec();
+        let Reverse(FairQueueItem(entity, vruntime)) = entities.swap_remove(index);
+        busiest.entities = BinaryHeap::from(entities);
+
+        let fair_attr = &entity.as_thread().unwrap().sched_attr().fair;
+        busiest.total_weight -= fair_attr.weight.load(Relaxed);
+        let lag = vruntime.saturating_sub(busiest.min_vruntime);
+        fair_attr.vruntime.store(self.min_vruntime + lag, Relaxed);
+
+        self.enqueue(entity.clone(), None);
+        Some(entity)
+    }
+
+    /// Removes a ready-to-run thread, which is going to be enqueued to another scheduling
+    /// class.
+    ///
+    /// This method returns `false` if the thread is not in the run queue.
+    pub fn remove(&mut self, entity: &Arc<Task>) -> bool {
+        if let Some(index) = self.throttled.iter().position(|e| Arc::ptr_eq(e, entity)) {
+            self.throttled.swap_remove(index);
+            return true;
+        }
+
+        let Some(index) = self
+            .entities
+            .iter()
+            .position(|Reverse(item)| Arc::ptr_eq(&item.0, entity))
+        else {
+            return false;
+        };
+
+        // The iteration order of a `BinaryHeap` is the order of its underlying vector.
+        let mut entities = core::mem::take(&mut self.entities).into_vec();
+        entities.swap_remove(index);
+        self.entities = BinaryHeap::from(entities);
+
+        let fair_attr = &entity.as_thread().unwrap().sched_attr().fair;
+        self.total_weight -= fair_attr.weight.load(Relaxed);
+        true
+    }
+
+    /// Moves the throttled threads whose CPU bandwidth is refilled back to the run queue.
+    fn unthrottle(&mut self) {
+        if self.throttled.is_empty() {
+            return;
+        }
+
+        let throttled = core::mem::take(&mut self.throttled);
+        for entity in throttled {
+            if entity.as_thread().unwrap().sched_attr().fair.is_throttled() {
+                self.throttled.push(entity);
+            } else {
+                self.enqueue(entity, None);
+            }
+        }
+    }
 }
 
 impl SchedClassRq for FairClassRq {
@@ -236,20 +343,29 @@ impl SchedClassRq for FairClassRq {
     }
 
     fn len(&self) -> usize {
-        self.entities.len()
+        self.entities.len() + self.throttled.len()
     }
 
     fn is_empty(&self) -> bool {
-        self.entities.is_empty()
+        self.entities.is_empty() && self.throttled.is_empty()
     }
 
     fn pick_next(&mut self) -> Option<Arc<Task>> {
-        let Reverse(FairQueueItem(entity, _)) = self.entities.pop()?;
+        self.unthrottle();
 
-        let sched_attr = entity.as_thread().unwrap().sched_attr();
-        self.total_weight -= sched_attr.fair.weight.load(Relaxed);
+        loop {
+            let Reverse(FairQueueItem(entity, _)) = self.entities.pop()?;
 
-        Some(entity)
+            let sched_attr = entity.as_thread().unwrap().sched_attr();
+            self.total_weight -= sched_attr.fair.weight.load(Relaxed);
+
+            if sched_attr.fair.is_throttled() {
+                self.throttled.push(entity);
+                continue;
+            }
+
+            return Some(entity);
+        }
     }
 
     fn update_current(
@@ -258,6 +374,8 @@ impl SchedClassRq for FairClassRq {
         attr: &SchedAttr,
         flags: UpdateFlags,
     ) -> bool {
+        let is_throttled = attr.fair.charge_bandwidth(rt.delta);
+
         match flags {
             UpdateFlags::Yield => true,
             UpdateFlags::Tick | UpdateFlags::Wait => {
@@ -267,7 +385,8 @@ impl SchedClassRq for FairClassRq {
                     None => vruntime,
                 };
 
-                rt.period_delta > self.time_slice(weight)
+                is_throttled
+                    || rt.period_delta > self.time_slice(weight)
                     || vruntime > self.min_vruntime + self.vtime_slice()
             }
         }
@@ -5,9 +5,10 @@
 use alloc::{boxed::Box, sync::Arc};
 use core::{fmt, sync::atomic::Ordering};
 
+use aster_trace::{declare_tracepoint, tracepoint};
 use ostd::{
     arch::read_tsc as sched_clock,
-    cpu::{all_cpus, CpuId, PinCurrentCpu},
+    cpu::{all_cpus, current_cpu_racy, CpuId, PinCurrentCpu},
     sync::SpinLock,
     task::{
         scheduler::{
@@ -16,17 +17,27 @@ use ostd::{
         },
         AtomicCpuId, Task,
     },
-    trap::disable_local,
+    timer,
+    trap::{disable_local, DisabledLocalIrqGuard},
 };
+use spin::Once;
 
 use super::{
+    isolation::is_isolated,
     nice::Nice,
-    stats::{set_stats_from_scheduler, SchedulerStats},
+    stats::{
+        set_stats_from_scheduler, CpuSchedStat, SchedulerStats, TaskSchedCounters, TaskSchedStat,
+    },
+    tick,
+};
+use crate::{
+    process::posix_thread::AsPosixThread,
+    thread::{AsThread, Thread, Tid},
 };
-use crate::thread::{AsThread, Thread};
 
+mod bandwidth;
 mod policy;
-mod time;
+pub(super) mod time;
 
 mod fair;
 mod idle;
@@ -35,14 +46,29 @@ mod stop;
 
 use self::policy::{SchedPolicyKind, SchedPolicyState};
 pub use self::{
+    bandwidth::{CpuBandwidth, CpuBandwidthStat},
     policy::SchedPolicy,
     real_time::{RealTimePolicy, RealTimePriority},
 };
 
 type SchedEntity = (Arc<Task>, Arc<Thread>);
 
+declare_tracepoint! {
+    /// Emitted when a thread is put into the run queue of a CPU.
+    static SCHED_WAKEUP: sched:sched_wakeup(tid, target_cpu);
+    /// Emitted when a CPU switches to another thread.
+    ///
+    /// The kernel threads, including the idle threads, are shown with a TID of zero.
+    static SCHED_SWITCH: sched:sched_switch(prev_tid, next_tid);
+}
+
+static SCHEDULER: Once<&'static ClassScheduler> = Once::new();
+
 pub fn init() {
+    aster_trace::register_tracepoints(&[&SCHED_WAKEUP, &SCHED_SWITCH]);
+
     let scheduler = Box::leak(Box::new(ClassScheduler::new()));
+    SCHEDULER.call_once(|| scheduler);
 
     // Inject the scheduler into the ostd for actual scheduling work.
     inject_scheduler(scheduler);
@@ -70,6 +96,9 @@ struct PerCpuClassRqSet {
     fair: fair::FairClassRq,
     idle: idle::IdleClassRq,
     current: Option<(SchedEntity, CurrentRuntime)>,
+    stat: CpuSchedStat,
+    /// The number of the timer ticks since the last scheduler tick.
+    nr_timer_ticks: u32,
 }
 
 /// Stores the runtime information of the current task.
@@ -133,6 +162,7 @@ pub struct SchedAttr {
     last_cpu: AtomicCpuId,
     real_time: real_time::RealTimeAttr,
     fair: fair::FairAttr,
+    stat: TaskSchedCounters,
 }
 
 impl SchedAttr {
@@ -152,6 +182,7 @@ impl SchedAttr {
                 SchedPolicy::Fair(nice) => nice,
                 _ => Nice::default(),
             }),
+            stat: TaskSchedCounters::default(),
         }
     }
 
@@ -169,19 +200,80 @@ impl SchedAttr {
     /// Specifically for real-time policies, if the new policy doesn't
     /// specify a base slice factor for RR, the old one will be kept.
     pub fn set_policy(&self, policy: SchedPolicy) {
-        self.policy.set(policy, |policy| match policy {
-            SchedPolicy::RealTime { rt_prio, rt_policy } => {
-                self.real_time.update(rt_prio.get(), rt_policy);
+        self.policy.set(policy, |base, effective| {
+            if let SchedPolicy::Fair(nice) = base {
+                self.fair.update(nice);
             }
-            SchedPolicy::Fair(nice) => self.fair.update(nice),
-            _ => {}
+            self.update_real_time(effective);
         });
     }
 
+    /// Retrieves the effective scheduling policy of the thread.
+    ///
+    /// This is the scheduling policy of the thread, unless the thread inherits a more urgent one
+    /// from the threads waiting for its [`PiMutex`]es.
+    ///
+    /// [`PiMutex`]: crate::sched::PiMutex
+    pub(in crate::sched) fn effective_policy(&self) -> SchedPolicy {
+        self.policy.effective()
+    }
+
+    /// Inherits the scheduling policies of the threads that start to wait for a [`PiMutex`]
+    /// held by the thread.
+    ///
+    /// If the effective policy of the thread becomes more urgent, the thread is moved to the
+    /// corresponding run queue if it is ready to run.
+    ///
+    /// [`PiMutex`]: crate::sched::PiMutex
+    pub(in crate::sched) fn inherit_policies(&self, thread: &Thread, policies: &[SchedPolicy]) {
+        let is_changed = self.policy.update_inherited(
+            |inherited| inherited.extend_from_slice(policies),
+            |effective| self.update_real_time(effective),
+        );
+        if is_changed && let Some(scheduler) = SCHEDULER.get() {
+            scheduler.requeue(&thread.task());
+        }
+    }
+
+    /// Stops inheriting the scheduling policies of the threads that wait for a [`PiMutex`]
+    /// that is released by the thread.
+    ///
+    /// [`PiMutex`]: crate::sched::PiMutex
+    pub(in crate::sched) fn uninherit_policies(&self, policies: &[SchedPolicy]) {
+        self.policy.update_inherited(
+            |inherited| {
+                for policy in policies {
+                    if let Some(index) = inherited.iter().position(|p| p == policy) {
+                        inherited.swap_remove(index);
+                    }
+                }
+            },
+            // The thread is running, so it will be enqueued with the new policy when it is
+            // preempted.
+            |effective| self.update_real_time(effective),
+        );
+    }
+
+    fn update_real_time(&self, effective: SchedPolicy) {
+        if let SchedPolicy::RealTime { rt_prio, rt_policy } = effective {
+            self.real_time.update(rt_prio.get(), rt_policy);
+        }
+    }
+
     pub fn update_policy<T>(&self, f: impl FnOnce(&mut SchedPolicy) -> T) -> T {
         self.policy.update(f)
     }
 
+    /// Sets the CPU bandwidth that limits the thread in the FAIR scheduling class.
+    pub fn set_cpu_bandwidth(&self, bandwidth: Option<Arc<CpuBandwidth>>) {
+        self.fair.set_bandwidth(bandwidth);
+    }
+
+    /// Returns the scheduling statistics of the thread.
+    pub fn sched_stat(&self) -> TaskSchedStat {
+        self.stat.get()
+    }
+
     fn last_cpu(&self) -> Option<CpuId> {
         self.last_cpu.get()
     }
@@ -218,18 +310,40 @@ impl Scheduler for ClassScheduler {
             .current
             .as_ref()
             .is_none_or(|((_, rq_current_thread), _)| {
-                thread.sched_attr().policy() < rq_current_thread.sched_attr().policy()
+                thread.sched_attr().effective_policy()
+                    < rq_current_thread.sched_attr().effective_policy()
             });
 
+        if flags == EnqueueFlags::Wake {
+            rq.stat.nr_wakeups += 1;
+            // The IRQs are disabled with the run queue locked, so the current CPU cannot change.
+            if current_cpu_racy() == cpu {
+                rq.stat.nr_local_wakeups += 1;
+            }
+        }
+
         thread.sched_attr().set_last_cpu(cpu);
+        tracepoint!(SCHED_WAKEUP, thread_tid(&thread), cpu.as_usize());
         rq.enqueue_entity((task, thread), Some(flags));
 
+        // The timer is checked with the run queue locked, so it cannot be stopped after the
+        // check with the new thread in the run queue. See `try_stop_tick`.
+        let is_tick_stopped = timer::is_tick_stopped(cpu);
+        drop(rq);
+        if is_tick_stopped {
+            tick::kick(cpu);
+        }
+
         should_preempt.then_some(cpu)
     }
 
     fn local_mut_rq_with(&self, f: &mut dyn FnMut(&mut dyn LocalRunQueue)) {
         let guard = disable_local();
-        let mut lock = self.rqs[guard.current_cpu().as_usize()].lock();
+        let cpu = guard.current_cpu();
+        let mut lock = self.rqs[cpu.as_usize()].lock();
+        if lock.is_idle() && !is_isolated(cpu) {
+            self.pull_fair_entity(cpu, &mut lock);
+        }
         f(&mut *lock)
     }
 
@@ -248,6 +362,8 @@ impl ClassScheduler {
                 fair: fair::FairClassRq::new(cpu),
                 idle: idle::IdleClassRq::new(),
                 current: None,
+                stat: CpuSchedStat::default(),
+                nr_timer_ticks: 0,
             })
         };
         ClassScheduler {
@@ -263,7 +379,17 @@ impl ClassScheduler {
         }
         debug_assert!(flags == EnqueueFlags::Spawn);
         let guard = disable_local();
-        let affinity = thread.atomic_cpu_affinity().load(Ordering::Relaxed);
+        let mut affinity = thread.atomic_cpu_affinity().load(Ordering::Relaxed);
+        // The isolated CPUs are selected only if the thread cannot run on the other CPUs.
+        let mut housekeeping = affinity.clone();
+        for cpu in affinity.iter() {
+            if is_isolated(cpu) {
+                housekeeping.remove(cpu);
+            }
+        }
+        if !housekeeping.is_empty() {
+            affinity = housekeeping;
+        }
         let mut selected = guard.current_cpu();
         let mut minimum_load = u32::MAX;
         let last_chosen = match self.last_chosen_cpu.get() {
@@ -294,6 +420,57 @@ impl ClassScheduler {
         self.last_chosen_cpu.set_anyway(selected);
         selected
     }
+
+    /// Moves a ready-to-run task to the run queue of its effective scheduling policy.
+    ///
+    /// This is called after the effective policy of the task is changed. Nothing is done if the
+    /// task is running or sleeping, since it will be enqueued with the new policy later.
+    ///
+    /// If the task becomes more urgent than the current task of its CPU, the current task will be
+    /// preempted at the next timer tick.
+    fn requeue(&self, task: &Arc<Task>) {
+        let Some(cpu) = task.cpu().get() else {
+            return;
+        };
+        let mut rq = self.rqs[cpu.as_usize()].disable_irq().lock();
+
+        // The task may have been dequeued or migrated before the run queue is locked.
+        if task.cpu().get() != Some(cpu)
+            || rq
+                .current
+                .as_ref()
+                .is_some_and(|((current, _), _)| Arc::ptr_eq(current, task))
+        {
+            return;
+        }
+
+        if rq.fair.remove(task) || rq.real_time.remove(task) {
+            let thread = task.as_thread().unwrap().clone();
+            rq.enqueue_entity((task.clone(), thread), None);
+        }
+    }
+
+    /// Pulls a FAIR thread from the busiest CPU to the idle CPU.
+    ///
+    /// This is checked whenever the local run queue is accessed, including on every timer tick.
+    /// So a CPU that becomes idle soon takes over the threads waiting on other CPUs. The other
+    /// run queues are only tried to lock, because locking them while holding the local run queue
+    /// may deadlock. The isolated CPUs neither pull the threads nor are pulled from.
+    fn pull_fair_entity(&self, cpu: CpuId, rq: &mut PerCpuClassRqSet) {
+        let busiest = all_cpus()
+            .filter(|&other| other != cpu && !is_isolated(other))
+            .filter_map(|other| self.rqs[other.as_usize()].try_lock())
+            .filter(|other| !other.fair.is_empty())
+            .max_by_key(|other| other.fair.len());
+        let Some(mut busiest) = busiest else {
+            return;
+        };
+
+        if let Some(task) = rq.fair.pull_from(&mut busiest.fair) {
+            task.cpu().set_anyway(cpu);
+            task.as_thread().unwrap().sched_attr().set_last_cpu(cpu);
+        }
+    }
 }
 
 impl PerCpuClassRqSet {
@@ -309,6 +486,7 @@ impl PerCpuClassRqSet {
     }
 
     fn enqueue_entity(&mut self, (task, thread): SchedEntity, flags: Option<EnqueueFlags>) {
+        thread.sched_attr().stat.on_queued(sched_clock());
         match thread.sched_attr().policy_kind() {
             SchedPolicyKind::Stop => self.stop.enqueue(task, flags),
             SchedPolicyKind::RealTime => self.real_time.enqueue(task, flags),
@@ -317,9 +495,35 @@ impl PerCpuClassRqSet {
         }
     }
 
+    /// Returns whether the CPU has nothing to run except for the idle entity.
+    fn is_idle(&self) -> bool {
+        self.stop.is_empty()
+            && self.real_time.is_empty()
+            && self.fair.is_empty()
+            && self.current.as_ref().is_none_or(|((_, thread), _)| {
+                thread.sched_attr().policy_kind() == SchedPolicyKind::Idle
+            })
+    }
+
+    /// Updates the scheduling statistics when the thread is picked to run.
+    fn account_picked(&mut self, thread: &Thread) {
+        let attr = thread.sched_attr();
+        self.stat.nr_schedules += 1;
+        if attr.policy_kind() == SchedPolicyKind::Idle {
+            self.stat.nr_idle_schedules += 1;
+            return;
+        }
+
+        self.stat.run_delay_ns += attr.stat.on_picked(sched_clock());
+        self.stat.nr_slices += 1;
+    }
+
+    /// Returns the number of the queued and running threads, excluding the idle entity.
     fn nr_queued_and_running(&self) -> (u32, u32) {
-        let queued = self.stop.len() + self.real_time.len() + self.fair.len() + self.idle.len();
-        let running = usize::from(self.current.is_some());
+        let queued = self.stop.len() + self.real_time.len() + self.fair.len();
+        let running = usize::from(self.current.as_ref().is_some_and(|((_, thread), _)| {
+            thread.sched_attr().policy_kind() != SchedPolicyKind::Idle
+        }));
         (queued as u32, running as u32)
     }
 }
@@ -331,6 +535,19 @@ impl LocalRunQueue for PerCpuClassRqSet {
 
     fn pick_next_current(&mut self) -> Option<&Arc<Task>> {
         self.pick_next_entity().and_then(|next| {
+            tracepoint!(
+                SCHED_SWITCH,
+                self.current
+                    .as_ref()
+                    .map_or(0, |((_, prev_thread), _)| thread_tid(prev_thread)),
+                thread_tid(&next.1)
+            );
+            self.account_picked(&next.1);
+            // The timer is stopped only while the CPU runs a single thread.
+            let was_busy = self.current.as_ref().is_some_and(|((_, prev_thread), _)| {
+                prev_thread.sched_attr().policy_kind() != SchedPolicyKind::Idle
+            });
+            tick::restart(&disable_local(), was_busy);
             // We guarantee that a task can appear at once in a `PerCpuClassRqSet`. So, the `next` cannot be the same
             // as the current task here.
             if let Some((old, _)) = self.current.replace((next, CurrentRuntime::new())) {
@@ -341,10 +558,28 @@ impl LocalRunQueue for PerCpuClassRqSet {
     }
 
     fn update_current(&mut self, flags: UpdateFlags) -> bool {
+        // The time since the last scheduler tick is accounted at the next one.
+        if flags == UpdateFlags::Tick {
+            self.nr_timer_ticks += 1;
+            if self.nr_timer_ticks < tick::timer_ticks_per_sched_tick() {
+                return false;
+            }
+            self.nr_timer_ticks = 0;
+        }
+
         if let Some(((_, cur), rt)) = &mut self.current {
             rt.update();
             let attr = &cur.sched_attr();
 
+            if attr.policy_kind() != SchedPolicyKind::Idle {
+                let run_time_ns = time::clocks_to_ns(rt.delta);
+                attr.stat.on_run(run_time_ns);
+                self.stat.run_time_ns += run_time_ns;
+            }
+            if flags == UpdateFlags::Yield {
+                self.stat.nr_yields += 1;
+            }
+
             let (current_expired, lookahead) = match attr.policy_kind() {
                 SchedPolicyKind::Stop => (self.stop.update_current(rt, attr, flags), 0),
                 SchedPolicyKind::RealTime => (self.real_time.update_current(rt, attr, flags), 1),
@@ -369,6 +604,34 @@ impl LocalRunQueue for PerCpuClassRqSet {
     }
 }
 
+/// Stops the timer of the current CPU if it runs a single user thread or nothing.
+///
+/// This is called at the timer interrupts of the `nohz_full=` CPUs.
+pub(super) fn try_stop_tick(irq_guard: &DisabledLocalIrqGuard) {
+    let scheduler = SCHEDULER.get().unwrap();
+    // The run queue may be locked by the interrupted code. The timer will be tried to stop at
+    // the next tick.
+    let Some(rq) = scheduler.rqs[irq_guard.current_cpu().as_usize()].try_lock() else {
+        return;
+    };
+
+    let (queued, _) = rq.nr_queued_and_running();
+    let is_user_or_idle = rq.current.as_ref().is_none_or(|((_, thread), _)| {
+        thread.as_posix_thread().is_some()
+            || thread.sched_attr().policy_kind() == SchedPolicyKind::Idle
+    });
+    if queued == 0 && is_user_or_idle {
+        tick::stop(irq_guard);
+    }
+}
+
+/// Returns the TID of a thread, or zero for a kernel thread.
+fn thread_tid(thread: &Thread) -> Tid {
+    thread
+        .as_posix_thread()
+        .map_or(0, |posix_thread| posix_thread.tid())
+}
+
 impl SchedulerStats for ClassScheduler {
     fn nr_queued_and_running(&self) -> (u32, u32) {
         self.rqs.iter().fold((0, 0), |(queued, running), rq| {
@@ -376,6 +639,10 @@ impl SchedulerStats for ClassScheduler {
             (queued + q, running + r)
         })
     }
+
+    fn cpu_sched_stat(&self, cpu: CpuId) -> CpuSchedStat {
+        self.rqs[cpu.as_usize()].disable_irq().lock().stat
+    }
 }
 
 impl Default for ClassScheduler {
@@ -1,5 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use alloc::vec::Vec;
 use core::sync::atomic::{AtomicU8, Ordering::Relaxed};
 
 use atomic_integer_wrapper::define_atomic_version_of_integer_like_type;
@@ -56,15 +57,37 @@ impl From<SchedPolicyKind> for u8 {
 
 #[derive(Debug)]
 pub(super) struct SchedPolicyState {
+    /// The kind of the effective policy.
     kind: AtomicSchedPolicyKind,
-    policy: SpinLock<SchedPolicy>,
+    policies: SpinLock<Policies>,
+}
+
+#[derive(Debug)]
+struct Policies {
+    /// The policy chosen by the user.
+    base: SchedPolicy,
+    /// The policies inherited from the threads that wait for the PI mutexes held by the thread.
+    inherited: Vec<SchedPolicy>,
+}
+
+impl Policies {
+    /// Returns the most urgent one of the base policy and the inherited policies.
+    fn effective(&self) -> SchedPolicy {
+        self.inherited
+            .iter()
+            .copied()
+            .fold(self.base, SchedPolicy::min)
+    }
 }
 
 impl SchedPolicyState {
     pub fn new(policy: SchedPolicy) -> Self {
         Self {
             kind: AtomicSchedPolicyKind::new(policy.kind()),
-            policy: SpinLock::new(policy),
+            policies: SpinLock::new(Policies {
+                base: policy,
+                inherited: Vec::new(),
+            }),
         }
     }
 
@@ -73,11 +96,18 @@ impl SchedPolicyState {
     }
 
     pub fn get(&self) -> SchedPolicy {
-        *self.policy.disable_irq().lock()
+        self.policies.disable_irq().lock().base
     }
 
-    pub fn set(&self, mut policy: SchedPolicy, update: impl FnOnce(SchedPolicy)) {
-        let mut this = self.policy.disable_irq().lock();
+    pub fn effective(&self) -> SchedPolicy {
+        self.policies.disable_irq().lock().effective()
+    }
+
+    /// Sets the base policy.
+    ///
+    /// The `update` closure is called with the new base policy and the new effective policy.
+    pub fn set(&self, mut policy: SchedPolicy, update: impl FnOnce(SchedPolicy, SchedPolicy)) {
+        let mut this = self.policies.disable_irq().lock();
 
         // Keep the old base slice factor if the new policy doesn't specify one.
         if let (
@@ -92,17 +122,41 @@ impl SchedPolicyState {
                 rt_policy: RealTimePolicy::RoundRobin { base_slice_factor },
                 ..
             },
-        ) = (*this, &mut policy)
+        ) = (this.base, &mut policy)
         {
             *base_slice_factor = slot.or(*base_slice_factor);
         }
 
-        update(policy);
-        self.kind.store(policy.kind(), Relaxed);
-        *this = policy;
+        this.base = policy;
+        let effective = this.effective();
+        update(policy, effective);
+        self.kind.store(effective.kind(), Relaxed);
     }
 
     pub fn update<T>(&self, update: impl FnOnce(&mut SchedPolicy) -> T) -> T {
-        update(&mut *self.policy.disable_irq().lock())
+        update(&mut self.policies.disable_irq().lock().base)
+    }
+
+    /// Changes the inherited policies.
+    ///
+    /// If the effective policy is changed, the `update` closure is called with the new effective
+    /// policy and this method returns `true`.
+    pub fn update_inherited(
+        &self,
+        change: impl FnOnce(&mut Vec<SchedPolicy>),
+        update: impl FnOnce(SchedPolicy),
+    ) -> bool {
+        let mut this = self.policies.disable_irq().lock();
+
+        let old_effective = this.effective();
+        change(&mut this.inherited);
+        let new_effective = this.effective();
+        if new_effective == old_effective {
+            return false;
+        }
+
+        update(new_effective);
+        self.kind.store(new_effective.kind(), Relaxed);
+        true
     }
 }
@@ -128,6 +128,24 @@ impl PrioArray {
         }
         Some(thread)
     }
+
+    fn remove(&mut self, thread: &Arc<Task>) -> bool {
+        let Some((prio, index)) = self.map.iter_ones().find_map(|prio| {
+            let index = self.queue[prio]
+                .iter()
+                .position(|t| Arc::ptr_eq(t, thread))?;
+            Some((prio, index))
+        }) else {
+            return false;
+        };
+
+        let queue = &mut self.queue[prio];
+        queue.remove(index);
+        if queue.is_empty() {
+            self.map.set(prio, false);
+        }
+        true
+    }
 }
 
 /// The per-cpu run queue for the REAL-TIME scheduling class.
@@ -174,6 +192,18 @@ impl RealTimeClassRq {
     fn swap_arrays(&mut self) {
         self.index = !self.index;
     }
+
+    /// Removes a ready-to-run thread, which is going to be enqueued with another priority or
+    /// to another scheduling class.
+    ///
+    /// This method returns `false` if the thread is not in the run queue.
+    pub fn remove(&mut self, entity: &Arc<Task>) -> bool {
+        let is_removed = self.array.iter_mut().any(|array| array.remove(entity));
+        if is_removed {
+            self.nr_running -= 1;
+        }
+        is_removed
+    }
 }
 
 impl SchedClassRq for RealTimeClassRq {
@@ -39,7 +39,8 @@ pub fn get_loadavg() -> [FixedPoint; 3] {
 /// Updates the load average of the system.
 ///
 /// This function should be called periodically to update the load average.
-/// The `get_load` function should return the load (the number of queued tasks) of the system.
+/// The `get_load` function should return the load (the number of queued and running tasks) of the
+/// system.
 /// See `sched::stats::scheduler_stats::set_stats_from_scheduler()` for an example.
 pub fn update_loadavg<F>(get_load: F)
 where
@@ -1,6 +1,11 @@
 // SPDX-License-Identifier: MPL-2.0
 
 pub mod loadavg;
+mod schedstat;
 mod scheduler_stats;
 
-pub use scheduler_stats::{nr_queued_and_running, set_stats_from_scheduler, SchedulerStats};
+pub(super) use schedstat::TaskSchedCounters;
+pub use schedstat::{CpuSchedStat, TaskSchedStat};
+pub use scheduler_stats::{
+    cpu_sched_stat, nr_queued_and_running, set_stats_from_scheduler, SchedulerStats,
+};
@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The scheduling statistics, which tell how long the threads run and wait in the run queues.
+//!
+//! The statistics of the CPUs are exported in `/proc/schedstat`, and the ones of the threads are
+//! exported in `/proc/[pid]/schedstat`, both in the formats of Linux. So a slowdown can be
+//! attributed to the scheduling by comparing the time waiting in the run queues with the time
+//! running.
+//!
+//! Reference: <https://docs.kernel.org/scheduler/sched-stats.html>
+
+use core::sync::atomic::{AtomicU64, Ordering::Relaxed};
+
+use crate::sched::sched_class::time::clocks_to_ns;
+
+/// The scheduling statistics of a CPU.
+///
+/// The idle entity of the CPU is not counted, except in `nr_idle_schedules`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CpuSchedStat {
+    /// The number of times that the current thread yields the CPU
+    pub nr_yields: u64,
+    /// The number of times that a thread, including the idle entity, is picked to run
+    pub nr_schedules: u64,
+    /// The number of times that the idle entity is picked to run
+    pub nr_idle_schedules: u64,
+    /// The number of the threads that are woken up on the CPU
+    pub nr_wakeups: u64,
+    /// The number of the threads that are woken up on the CPU by the CPU itself
+    pub nr_local_wakeups: u64,
+    /// The total time that the threads run on the CPU, in nanoseconds
+    pub run_time_ns: u64,
+    /// The total time that the threads wait in the run queue of the CPU, in nanoseconds
+    pub run_delay_ns: u64,
+    /// The number of the time slices that run on the CPU
+    pub nr_slices: u64,
+}
+
+/// The scheduling statistics of a thread.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TaskSchedStat {
+    /// The total time that the thread runs, in nanoseconds
+    pub run_time_ns: u64,
+    /// The total time that the thread waits in the run queues, in nanoseconds
+    pub run_delay_ns: u64,
+    /// The number of the time slices that the thread runs
+    pub nr_slices: u64,
+}
+
+/// The counters of the scheduling statistics of a thread, which are updated by the scheduler.
+#[derive(Debug, Default)]
+pub(in crate::sched) struct TaskSchedCounters {
+    run_time_ns: AtomicU64,
+    run_delay_ns: AtomicU64,
+    nr_slices: AtomicU64,
+    /// The time when the thread is put into a run queue in TSC clocks, or zero if the thread is
+    /// not in any run queue
+    queued_at: AtomicU64,
+}
+
+impl TaskSchedCounters {
+    /// Records that the thread is put into a run queue at `now` in TSC clocks.
+    ///
+    /// If the thread is moved between the run queues, the time when it is first queued is kept.
+    pub(in crate::sched) fn on_queued(&self, now: u64) {
+        let _ = self.queued_at.compare_exchange(0, now, Relaxed, Relaxed);
+    }
+
+    /// Records that the thread is picked to run at `now` in TSC clocks, and returns the time that
+    /// it waits in the run queue in nanoseconds.
+    pub(in crate::sched) fn on_picked(&self, now: u64) -> u64 {
+        let queued_at = self.queued_at.swap(0, Relaxed);
+        let run_delay_ns = if queued_at == 0 {
+            0
+        } else {
+            clocks_to_ns(now.saturating_sub(queued_at))
+        };
+        self.run_delay_ns.fetch_add(run_delay_ns, Relaxed);
+        self.nr_slices.fetch_add(1, Relaxed);
+        run_delay_ns
+    }
+
+    /// Records that the thread has run for `run_time_ns` nanoseconds.
+    pub(in crate::sched) fn on_run(&self, run_time_ns: u64) {
+        self.run_time_ns.fetch_add(run_time_ns, Relaxed);
+    }
+
+    pub(in crate::sched) fn get(&self) -> TaskSchedStat {
+        TaskSchedStat {
+            run_time_ns: self.run_time_ns.load(Relaxed),
+            run_delay_ns: self.run_delay_ns.load(Relaxed),
+            nr_slices: self.nr_slices.load(Relaxed),
+        }
+    }
+}
@@ -1,9 +1,9 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use ostd::timer;
+use ostd::{cpu::CpuId, timer};
 use spin::Once;
 
-use super::loadavg;
+use super::{loadavg, CpuSchedStat};
 
 /// The global scheduler statistic singleton
 static SCHEDULER_STATS: Once<&'static dyn SchedulerStats> = Once::new();
@@ -18,7 +18,10 @@ pub fn set_stats_from_scheduler(scheduler: &'static dyn SchedulerStats) {
 
     // Register a callback to update the load average periodically
     timer::register_callback(|| {
-        loadavg::update_loadavg(|| nr_queued_and_running().0);
+        loadavg::update_loadavg(|| {
+            let (nr_queued, nr_running) = nr_queued_and_running();
+            nr_queued + nr_running
+        });
     });
 }
 
@@ -26,12 +29,22 @@ pub fn set_stats_from_scheduler(scheduler: &'static dyn SchedulerStats) {
 pub trait SchedulerStats: Sync + Send {
     /// Returns a tuple with the number of tasks in the runqueues and the number of running tasks.
     ///
+    /// The idle tasks of the CPUs are not counted.
+    ///
     /// We decided to return a tuple instead of having two separate functions to
     /// avoid the overhead of disabling the preemption twice to inspect the scheduler.
     fn nr_queued_and_running(&self) -> (u32, u32);
+
+    /// Returns the scheduling statistics of the CPU.
+    fn cpu_sched_stat(&self, cpu: CpuId) -> CpuSchedStat;
 }
 
 /// Get the amount of tasks in the runqueues and the amount of running tasks.
 pub fn nr_queued_and_running() -> (u32, u32) {
     SCHEDULER_STATS.get().unwrap().nr_queued_and_running()
 }
+
+/// Get the scheduling statistics of the CPU.
+pub fn cpu_sched_stat(cpu: CpuId) -> CpuSchedStat {
+    SCHEDULER_STATS.get().unwrap().cpu_sched_stat(cpu)
+}
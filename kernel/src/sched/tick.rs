@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The scheduler tick and the dynticks-full mode.
+//!
+//! The timer interrupts arrive at [`TIMER_FREQ`], but the current thread is only checked for
+//! preemption every `TIMER_FREQ / sched.tick_hz` interrupts. The time that the thread runs
+//! between the scheduler ticks is accounted at the next one, so a lower frequency gives fewer
+//! context switches at the cost of the scheduling latency.
+//!
+//! In the dynticks-full mode, the timer interrupts of a `nohz_full=` CPU are stopped while the CPU
+//! runs a single user thread, or nothing, so that the thread runs without being disturbed. The
+//! timer is restarted when another thread is put into the run queue of the CPU, in which case an
+//! IPI is sent to the CPU, or when the CPU switches to another thread. While the timer is stopped:
+//! - the ticks are accounted as the user time when the timer is restarted, or not accounted if
+//!   the CPU is idle, whose time is measured separately;
+//! - the stack samples of the profiler and the soft-lockup detector are not taken.
+//!
+//! The timer of the BSP is never stopped, since it updates the jiffies and fires the timers of
+//! all the CPUs.
+
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use ostd::{
+    arch::timer::TIMER_FREQ,
+    cpu::{CpuId, PinCurrentCpu},
+    cpu_local,
+    smp::inter_processor_call,
+    task::disable_preempt,
+    timer::{self, Jiffies},
+    trap::{self, DisabledLocalIrqGuard},
+};
+
+use super::{cpu_idle, isolation::is_nohz_full, sched_class};
+use crate::prelude::*;
+
+crate::kernel_param! {
+    /// The frequency of the scheduler tick in Hz, at which the current thread is checked for
+    /// preemption. It must divide the frequency of the timer interrupts, which is 1000 Hz.
+    static TICK_HZ: u32 = ("sched.tick_hz", TIMER_FREQ as u32);
+}
+
+static TIMER_TICKS_PER_SCHED_TICK: AtomicU32 = AtomicU32::new(1);
+
+cpu_local! {
+    /// The jiffies when the timer of the CPU is stopped.
+    static STOPPED_AT: AtomicU64 = AtomicU64::new(0);
+}
+
+pub(super) fn init() {
+    let mut hz = TICK_HZ.get();
+    if hz == 0 || TIMER_FREQ % hz as u64 != 0 {
+        warn!(
+            "`sched.tick_hz={}` does not divide {}, which is ignored",
+            hz, TIMER_FREQ
+        );
+        hz = TIMER_FREQ as u32;
+    }
+    TIMER_TICKS_PER_SCHED_TICK.store(TIMER_FREQ as u32 / hz, Ordering::Relaxed);
+    info!("The scheduler tick is at {} Hz", hz);
+}
+
+/// Enables the dynticks-full mode on the current application processor if it is a `nohz_full=`
+/// CPU.
+///
+/// This function should be called once on each application processor.
+pub(super) fn init_on_ap() {
+    let cpu = disable_preempt().current_cpu();
+    if is_nohz_full(cpu) {
+        timer::register_callback(|| sched_class::try_stop_tick(&trap::disable_local()));
+    }
+}
+
+/// Returns the number of the timer interrupts between two scheduler ticks.
+pub(super) fn timer_ticks_per_sched_tick() -> u32 {
+    TIMER_TICKS_PER_SCHED_TICK.load(Ordering::Relaxed)
+}
+
+/// Stops the timer of the current CPU.
+///
+/// This function should be called with the local run queue locked, so that a thread that is put
+/// into the run queue later sees the timer stopped and kicks the CPU.
+pub(super) fn stop(irq_guard: &DisabledLocalIrqGuard) {
+    if timer::stop_tick(irq_guard) {
+        STOPPED_AT
+            .get_with(irq_guard)
+            .store(Jiffies::elapsed().as_u64(), Ordering::Relaxed);
+    }
+}
+
+/// Restarts the timer of the current CPU if it is stopped.
+///
+/// The missed ticks are accounted as the user time if `is_busy` is true, i.e., the CPU has been
+/// running a thread rather than being idle.
+pub(super) fn restart(irq_guard: &DisabledLocalIrqGuard, is_busy: bool) {
+    if !timer::restart_tick(irq_guard) {
+        return;
+    }
+
+    if is_busy {
+        let stopped_at = STOPPED_AT.get_with(irq_guard).load(Ordering::Relaxed);
+        let missed_ticks = Jiffies::elapsed().as_u64().saturating_sub(stopped_at);
+        cpu_idle::account_user_ticks(irq_guard.current_cpu(), missed_ticks);
+    }
+}
+
+/// Restarts the timer of the CPU, which is stopped, by sending an IPI to it.
+pub(super) fn kick(cpu: CpuId) {
+    inter_processor_call(&cpu.into(), || {
+        let irq_guard = trap::disable_local();
+        let is_busy = !cpu_idle::is_idle(irq_guard.current_cpu());
+        restart(&irq_guard, is_busy);
+    });
+}
@@ -2,10 +2,7 @@
 
 use super::SyscallReturn;
 use crate::{
-    fs::{
-        file_table::{get_file_fast, FdFlags, FileDesc},
-        utils::{CreationFlags, StatusFlags},
-    },
+    fs::file_table::{get_file_fast, FdCreationFlags, FileDesc},
     prelude::*,
     util::net::write_socket_addr_to_user,
 };
@@ -18,7 +15,13 @@ pub fn sys_accept(
 ) -> Result<SyscallReturn> {
     debug!("sockfd = {sockfd}, sockaddr_ptr = 0x{sockaddr_ptr:x}, addrlen_ptr = 0x{addrlen_ptr:x}");
 
-    let fd = do_accept(sockfd, sockaddr_ptr, addrlen_ptr, Flags::empty(), ctx)?;
+    let fd = do_accept(
+        sockfd,
+        sockaddr_ptr,
+        addrlen_ptr,
+        FdCreationFlags::empty(),
+        ctx,
+    )?;
     Ok(SyscallReturn::Return(fd as _))
 }
 
@@ -30,7 +33,8 @@ pub fn sys_accept4(
     ctx: &Context,
 ) -> Result<SyscallReturn> {
     trace!("raw flags = 0x{:x}", flags);
-    let flags = Flags::from_bits_truncate(flags);
+    let flags = FdCreationFlags::from_bits(flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid flags"))?;
     debug!(
         "sockfd = {}, sockaddr_ptr = 0x{:x}, addrlen_ptr = 0x{:x}, flags = {:?}",
         sockfd, sockaddr_ptr, addrlen_ptr, flags
@@ -44,7 +48,7 @@ fn do_accept(
     sockfd: FileDesc,
     sockaddr_ptr: Vaddr,
     addrlen_ptr: Vaddr,
-    flags: Flags,
+    flags: FdCreationFlags,
     ctx: &Context,
 ) -> Result<FileDesc> {
     let mut file_table = ctx.thread_local.borrow_file_table_mut();
@@ -59,15 +63,7 @@ fn do_accept(
         })?
     };
 
-    if flags.contains(Flags::SOCK_NONBLOCK) {
-        connected_socket.set_status_flags(StatusFlags::O_NONBLOCK)?;
-    }
-
-    let fd_flags = if flags.contains(Flags::SOCK_CLOEXEC) {
-        FdFlags::CLOEXEC
-    } else {
-        FdFlags::empty()
-    };
+    let fd_flags = flags.apply_to(connected_socket.as_ref())?;
 
     if sockaddr_ptr != 0 {
         write_socket_addr_to_user(&socket_addr, sockaddr_ptr, addrlen_ptr)?;
@@ -75,18 +71,8 @@ fn do_accept(
 
     let fd = {
         let mut file_table_locked = file_table.unwrap().write();
-        file_table_locked.insert(connected_socket, fd_flags)
+        file_table_locked.insert(connected_socket, fd_flags)?
     };
 
     Ok(fd)
 }
-
-bitflags! {
-    struct Flags: u32 {
-        const SOCK_NONBLOCK = NONBLOCK;
-        const SOCK_CLOEXEC = CLOEXEC;
-    }
-}
-
-const NONBLOCK: u32 = StatusFlags::O_NONBLOCK.bits();
-const CLOEXEC: u32 = CreationFlags::O_CLOEXEC.bits();
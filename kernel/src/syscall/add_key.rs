@@ -0,0 +1,56 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    keyring::{self, KeyType, MAX_DESCRIPTION_LEN, MAX_PAYLOAD_SIZE},
+    prelude::*,
+};
+
+/// The maximum length of the name of a key type.
+const MAX_TYPE_NAME_LEN: usize = 32;
+
+pub fn sys_add_key(
+    type_addr: Vaddr,
+    description_addr: Vaddr,
+    payload_addr: Vaddr,
+    payload_len: usize,
+    keyring: i32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let user_space = ctx.user_space();
+    let type_name = user_space.read_cstring(type_addr, MAX_TYPE_NAME_LEN + 1)?;
+    let description = user_space.read_cstring(description_addr, MAX_DESCRIPTION_LEN + 1)?;
+    debug!(
+        "type = {:?}, description = {:?}, payload_len = {}, keyring = {}",
+        type_name, description, payload_len, keyring
+    );
+
+    let type_ = KeyType::from_name(&type_name.to_string_lossy())?;
+    if payload_len > MAX_PAYLOAD_SIZE {
+        return_errno_with_message!(Errno::EINVAL, "the key payload is too large");
+    }
+    let mut payload = vec![0; payload_len];
+    if payload_len != 0 {
+        user_space.read_bytes(payload_addr, &mut VmWriter::from(payload.as_mut_slice()))?;
+    }
+
+    // The keyrings are not implemented, so only the special keyrings are accepted and the keys
+    // are added to the global set.
+    if SpecialKeyring::try_from(keyring).is_err() {
+        return_errno_with_message!(Errno::ENOKEY, "the keyring does not exist");
+    }
+
+    let serial = keyring::add_key(type_, &description.to_string_lossy(), payload)?;
+    Ok(SyscallReturn::Return(serial as _))
+}
+
+/// The IDs of the special keyrings.
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[repr(i32)]
+enum SpecialKeyring {
+    Thread = -1,
+    Process = -2,
+    Session = -3,
+    User = -4,
+    UserSession = -5,
+}
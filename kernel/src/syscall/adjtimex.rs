@@ -0,0 +1,282 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use ostd::Pod;
+
+use super::{clock_gettime::ClockId, clock_settime::check_sys_time_capability, SyscallReturn};
+use crate::{
+    prelude::*,
+    time::{clockid_t, clocks::RealTimeClock, set_real_time, timeval_t, Clock},
+};
+
+pub fn sys_adjtimex(timex_addr: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    do_adjtimex(timex_addr, ctx)
+}
+
+pub fn sys_clock_adjtime(
+    clockid: clockid_t,
+    timex_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    match ClockId::try_from(clockid) {
+        Ok(ClockId::CLOCK_REALTIME) => do_adjtimex(timex_addr, ctx),
+        Ok(_) => return_errno_with_message!(Errno::EOPNOTSUPP, "the clock cannot be adjusted"),
+        Err(_) => return_errno_with_message!(Errno::EINVAL, "invalid clock ID"),
+    }
+}
+
+fn do_adjtimex(timex_addr: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    let user_space = ctx.user_space();
+    let mut timex = user_space.read_val::<timex_t>(timex_addr)?;
+    let modes = AdjModes::from_bits_truncate(timex.modes);
+    debug!("modes = {:?}", modes);
+
+    validate_timex(&timex, modes, ctx)?;
+
+    if modes.contains(AdjModes::ADJ_SETOFFSET) && !modes.contains(AdjModes::ADJ_ADJTIME) {
+        let nanos_per_unit = if modes.contains(AdjModes::ADJ_NANO) {
+            1
+        } else {
+            NSEC_PER_USEC
+        };
+        let offset = timex.time.sec as i128 * NSEC_PER_SEC as i128
+            + timex.time.usec as i128 * nanos_per_unit as i128;
+        let real_time = RealTimeClock::get().read_time().as_nanos() as i128 + offset;
+        let real_time = u64::try_from(real_time)
+            .map(Duration::from_nanos)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the real time is out of range"))?;
+        set_real_time(real_time)?;
+    }
+
+    let mut state = NTP_STATE.lock();
+    state.update(&timex, modes);
+
+    state.fill(&mut timex);
+    user_space.write_val(timex_addr, &timex)?;
+
+    let clock_state = if state.status & STA_UNSYNC != 0 {
+        TIME_ERROR
+    } else {
+        TIME_OK
+    };
+    Ok(SyscallReturn::Return(clock_state))
+}
+
+fn validate_timex(timex: &timex_t, modes: AdjModes, ctx: &Context) -> Result<()> {
+    if modes.contains(AdjModes::ADJ_ADJTIME) {
+        // The adjtime(3) mode only allows to set or read a single-shot offset.
+        if !modes.contains(AdjModes::ADJ_OFFSET) {
+            return_errno_with_message!(Errno::EINVAL, "the adjtime mode is invalid");
+        }
+        if !modes.contains(AdjModes::ADJ_OFFSET_READONLY) {
+            check_sys_time_capability(ctx)?;
+        }
+        return Ok(());
+    }
+
+    // Only reading the clock state is allowed without the capability.
+    if !modes.is_empty() {
+        check_sys_time_capability(ctx)?;
+    }
+
+    if modes.contains(AdjModes::ADJ_TICK) && !(MIN_TICK..=MAX_TICK).contains(&timex.tick) {
+        return_errno_with_message!(Errno::EINVAL, "the tick value is out of range");
+    }
+
+    if modes.contains(AdjModes::ADJ_SETOFFSET) {
+        let max_usec = if modes.contains(AdjModes::ADJ_NANO) {
+            NSEC_PER_SEC
+        } else {
+            USEC_PER_SEC
+        };
+        if !(0..max_usec).contains(&timex.time.usec) {
+            return_errno_with_message!(Errno::EINVAL, "the time offset is not normalized");
+        }
+    }
+
+    Ok(())
+}
+
+/// The NTP state of the system clock.
+///
+/// The values are recorded and reported back, but they do not discipline the system clock yet.
+/// Only [`AdjModes::ADJ_SETOFFSET`] takes effect on the time.
+struct NtpState {
+    /// The time offset in nanoseconds.
+    offset: i64,
+    freq: i64,
+    maxerror: i64,
+    esterror: i64,
+    status: i32,
+    constant: i64,
+    tick: i64,
+    tai: i32,
+}
+
+static NTP_STATE: SpinLock<NtpState> = SpinLock::new(NtpState {
+    offset: 0,
+    freq: 0,
+    maxerror: NTP_PHASE_LIMIT,
+    esterror: NTP_PHASE_LIMIT,
+    status: STA_UNSYNC,
+    constant: 2,
+    tick: USEC_PER_SEC / USER_HZ,
+    tai: 0,
+});
+
+impl NtpState {
+    fn update(&mut self, timex: &timex_t, modes: AdjModes) {
+        if modes.contains(AdjModes::ADJ_ADJTIME) {
+            // The offset is applied at once, so there is no remaining offset to report.
+            if !modes.contains(AdjModes::ADJ_OFFSET_READONLY) {
+                warn!("the single-shot time adjustment is not supported");
+            }
+            return;
+        }
+
+        if modes.contains(AdjModes::ADJ_STATUS) {
+            self.status = (self.status & STA_RONLY) | (timex.status & !STA_RONLY);
+        }
+        if modes.contains(AdjModes::ADJ_NANO) {
+            self.status |= STA_NANO;
+        }
+        if modes.contains(AdjModes::ADJ_MICRO) {
+            self.status &= !STA_NANO;
+        }
+        if modes.contains(AdjModes::ADJ_FREQUENCY) {
+            self.freq = timex.freq.clamp(-MAXFREQ_SCALED, MAXFREQ_SCALED);
+        }
+        if modes.contains(AdjModes::ADJ_MAXERROR) {
+            self.maxerror = timex.maxerror.clamp(0, NTP_PHASE_LIMIT);
+        }
+        if modes.contains(AdjModes::ADJ_ESTERROR) {
+            self.esterror = timex.esterror.clamp(0, NTP_PHASE_LIMIT);
+        }
+        if modes.contains(AdjModes::ADJ_TIMECONST) {
+            self.constant = timex.constant.clamp(0, MAXTC);
+        }
+        if modes.contains(AdjModes::ADJ_TAI) && timex.constant >= 0 {
+            self.tai = timex.constant as i32;
+        }
+        if modes.contains(AdjModes::ADJ_OFFSET) {
+            let offset = if self.status & STA_NANO != 0 {
+                timex.offset
+            } else {
+                timex.offset.saturating_mul(NSEC_PER_USEC)
+            };
+            self.offset = offset.clamp(-MAXPHASE, MAXPHASE);
+        }
+        if modes.contains(AdjModes::ADJ_TICK) {
+            self.tick = timex.tick;
+        }
+    }
+
+    fn fill(&self, timex: &mut timex_t) {
+        let now = RealTimeClock::get().read_time();
+        let is_nano = self.status & STA_NANO != 0;
+
+        *timex = timex_t {
+            modes: timex.modes,
+            offset: if is_nano {
+                self.offset
+            } else {
+                self.offset / NSEC_PER_USEC
+            },
+            freq: self.freq,
+            maxerror: self.maxerror,
+            esterror: self.esterror,
+            status: self.status,
+            constant: self.constant,
+            precision: 1,
+            tolerance: MAXFREQ_SCALED,
+            time: timeval_t {
+                sec: now.as_secs() as _,
+                usec: if is_nano {
+                    now.subsec_nanos() as _
+                } else {
+                    now.subsec_micros() as _
+                },
+            },
+            tick: self.tick,
+            tai: self.tai,
+            ..Default::default()
+        };
+    }
+}
+
+bitflags! {
+    struct AdjModes: u32 {
+        const ADJ_OFFSET = 0x0001;
+        const ADJ_FREQUENCY = 0x0002;
+        const ADJ_MAXERROR = 0x0004;
+        const ADJ_ESTERROR = 0x0008;
+        const ADJ_STATUS = 0x0010;
+        const ADJ_TIMECONST = 0x0020;
+        const ADJ_TAI = 0x0080;
+        const ADJ_SETOFFSET = 0x0100;
+        const ADJ_MICRO = 0x1000;
+        const ADJ_NANO = 0x2000;
+        const ADJ_TICK = 0x4000;
+        /// The adjtime(3) mode, which is only used with `ADJ_OFFSET` (i.e.,
+        /// `ADJ_OFFSET_SINGLESHOT`) and `ADJ_OFFSET_READONLY` (i.e., `ADJ_OFFSET_SS_READ`).
+        const ADJ_ADJTIME = 0x8000;
+        /// The same bit as `ADJ_NANO`, but only meaningful in the adjtime(3) mode.
+        const ADJ_OFFSET_READONLY = 0x2000;
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, Pod)]
+#[expect(non_camel_case_types)]
+struct timex_t {
+    modes: u32,
+    _pad0: u32,
+    offset: i64,
+    freq: i64,
+    maxerror: i64,
+    esterror: i64,
+    status: i32,
+    _pad1: u32,
+    constant: i64,
+    precision: i64,
+    tolerance: i64,
+    time: timeval_t,
+    tick: i64,
+    ppsfreq: i64,
+    jitter: i64,
+    shift: i32,
+    _pad2: u32,
+    stabil: i64,
+    jitcnt: i64,
+    calcnt: i64,
+    errcnt: i64,
+    stbcnt: i64,
+    tai: i32,
+    _reserved: [u32; 11],
+}
+
+const TIME_OK: isize = 0;
+const TIME_ERROR: isize = 5;
+
+const STA_UNSYNC: i32 = 0x0040;
+const STA_NANO: i32 = 0x2000;
+/// The read-only status bits.
+const STA_RONLY: i32 = 0xff00u32 as i32;
+
+const NSEC_PER_USEC: i64 = 1_000;
+const USEC_PER_SEC: i64 = 1_000_000;
+const NSEC_PER_SEC: i64 = 1_000_000_000;
+
+const USER_HZ: i64 = 100;
+const MIN_TICK: i64 = 900_000 / USER_HZ;
+const MAX_TICK: i64 = 1_100_000 / USER_HZ;
+
+/// The maximum frequency error (500 PPM) in the scaled PPM (i.e., PPM << 16).
+const MAXFREQ_SCALED: i64 = 500 << 16;
+/// The maximum time error in microseconds.
+const NTP_PHASE_LIMIT: i64 = 16_000_000;
+/// The maximum phase offset in nanoseconds.
+const MAXPHASE: i64 = 500_000_000;
+/// The maximum time constant.
+const MAXTC: i64 = 10;
@@ -3,7 +3,10 @@
 use crate::syscall::{
     accept::{sys_accept, sys_accept4},
     access::{sys_faccessat, sys_faccessat2},
+    add_key::sys_add_key,
+    adjtimex::{sys_adjtimex, sys_clock_adjtime},
     bind::sys_bind,
+    bpf::sys_bpf,
     brk::sys_brk,
     capget::sys_capget,
     capset::sys_capset,
@@ -11,9 +14,10 @@ use crate::syscall::{
     chmod::{sys_fchmod, sys_fchmodat},
     chown::{sys_fchown, sys_fchownat},
     chroot::sys_chroot,
-    clock_gettime::sys_clock_gettime,
+    clock_gettime::{sys_clock_getres, sys_clock_gettime},
+    clock_settime::sys_clock_settime,
     clone::{sys_clone, sys_clone3},
-    close::sys_close,
+    close::{sys_close, sys_close_range},
     connect::sys_connect,
     dup::{sys_dup, sys_dup3},
     epoll::{sys_epoll_create1, sys_epoll_ctl, sys_epoll_pwait},
@@ -21,12 +25,14 @@ use crate::syscall::{
     execve::{sys_execve, sys_execveat},
     exit::sys_exit,
     exit_group::sys_exit_group,
+    fadvise64::sys_fadvise64,
     fallocate::sys_fallocate,
     fcntl::sys_fcntl,
     flock::sys_flock,
     fsync::{sys_fdatasync, sys_fsync},
     futex::sys_futex,
     get_priority::sys_get_priority,
+    get_robust_list::sys_get_robust_list,
     getcpu::sys_getcpu,
     getcwd::sys_getcwd,
     getdents64::sys_getdents64,
@@ -49,7 +55,11 @@ use crate::syscall::{
     gettimeofday::sys_gettimeofday,
     getuid::sys_getuid,
     impl_syscall_nums_and_dispatch_fn,
+    inotify::{sys_inotify_add_watch, sys_inotify_init1, sys_inotify_rm_watch},
+    io_uring::{sys_io_uring_enter, sys_io_uring_register, sys_io_uring_setup},
     ioctl::sys_ioctl,
+    ioprio::{sys_ioprio_get, sys_ioprio_set},
+    kcmp::sys_kcmp,
     kill::sys_kill,
     link::sys_linkat,
     listen::sys_listen,
@@ -70,16 +80,19 @@ use crate::syscall::{
     preadv::{sys_preadv, sys_preadv2, sys_readv},
     prlimit64::{sys_getrlimit, sys_prlimit64, sys_setrlimit},
     pselect6::sys_pselect6,
+    ptrace::sys_ptrace,
     pwrite64::sys_pwrite64,
     pwritev::{sys_pwritev, sys_pwritev2, sys_writev},
     read::sys_read,
     readlink::sys_readlinkat,
+    reboot::sys_reboot,
     recvfrom::sys_recvfrom,
     recvmsg::sys_recvmsg,
     rename::sys_renameat,
     rt_sigaction::sys_rt_sigaction,
     rt_sigpending::sys_rt_sigpending,
     rt_sigprocmask::sys_rt_sigprocmask,
+    rt_sigqueueinfo::{sys_rt_sigqueueinfo, sys_rt_tgsigqueueinfo},
     rt_sigsuspend::sys_rt_sigsuspend,
     sched_affinity::{sys_sched_getaffinity, sys_sched_setaffinity},
     sched_get_priority_max::sys_sched_get_priority_max,
@@ -91,6 +104,7 @@ use crate::syscall::{
     sched_setparam::sys_sched_setparam,
     sched_setscheduler::sys_sched_setscheduler,
     sched_yield::sys_sched_yield,
+    seccomp::sys_seccomp,
     semctl::sys_semctl,
     semget::sys_semget,
     semop::{sys_semop, sys_semtimedop},
@@ -104,6 +118,7 @@ use crate::syscall::{
     setfsuid::sys_setfsuid,
     setgid::sys_setgid,
     setgroups::sys_setgroups,
+    sethostname::{sys_setdomainname, sys_sethostname},
     setitimer::{sys_getitimer, sys_setitimer},
     setpgid::sys_setpgid,
     setregid::sys_setregid,
@@ -112,7 +127,12 @@ use crate::syscall::{
     setreuid::sys_setreuid,
     setsid::sys_setsid,
     setsockopt::sys_setsockopt,
+    settimeofday::sys_settimeofday,
     setuid::sys_setuid,
+    shmat::sys_shmat,
+    shmctl::sys_shmctl,
+    shmdt::sys_shmdt,
+    shmget::sys_shmget,
     shutdown::sys_shutdown,
     sigaltstack::sys_sigaltstack,
     signalfd::sys_signalfd4,
@@ -123,7 +143,8 @@ use crate::syscall::{
     statx::sys_statx,
     symlink::sys_symlinkat,
     sync::sys_sync,
-    tgkill::sys_tgkill,
+    syslog::sys_syslog,
+    tgkill::{sys_tgkill, sys_tkill},
     timer_create::{sys_timer_create, sys_timer_delete},
     timer_settime::{sys_timer_gettime, sys_timer_settime},
     timerfd_create::sys_timerfd_create,
@@ -134,6 +155,7 @@ use crate::syscall::{
     umount::sys_umount,
     uname::sys_uname,
     unlink::sys_unlinkat,
+    unshare::sys_unshare,
     utimens::sys_utimensat,
     wait4::sys_wait4,
     waitid::sys_waitid,
@@ -149,7 +171,12 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_DUP = 23                 => sys_dup(args[..1]);
     SYS_DUP3 = 24                => sys_dup3(args[..3]);
     SYS_FCNTL = 25               => sys_fcntl(args[..3]);
+    SYS_INOTIFY_INIT1 = 26       => sys_inotify_init1(args[..1]);
+    SYS_INOTIFY_ADD_WATCH = 27   => sys_inotify_add_watch(args[..3]);
+    SYS_INOTIFY_RM_WATCH = 28    => sys_inotify_rm_watch(args[..2]);
     SYS_IOCTL = 29               => sys_ioctl(args[..3]);
+    SYS_IOPRIO_SET = 30          => sys_ioprio_set(args[..3]);
+    SYS_IOPRIO_GET = 31          => sys_ioprio_get(args[..2]);
     SYS_FLOCK = 32               => sys_flock(args[..2]);
     SYS_MKNODAT = 33             => sys_mknodat(args[..4]);
     SYS_MKDIRAT = 34             => sys_mkdirat(args[..3]);
@@ -194,20 +221,33 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_SYNC = 81                => sys_sync(args[..0]);
     SYS_FSYNC = 82               => sys_fsync(args[..1]);
     SYS_FDATASYNC = 83           => sys_fdatasync(args[..1]);
-    SYS_TIMERFD_CREATE = 85        => sys_timerfd_create(args[..2]);
+    SYS_TIMERFD_CREATE = 85      => sys_timerfd_create(args[..2]);
+    SYS_TIMERFD_SETTIME = 86     => sys_timerfd_settime(args[..4]);
+    SYS_TIMERFD_GETTIME = 87     => sys_timerfd_gettime(args[..2]);
+    SYS_UTIMENSAT = 88           => sys_utimensat(args[..4]);
     SYS_CAPGET = 90              => sys_capget(args[..2]);
     SYS_CAPSET = 91              => sys_capset(args[..2]);
     SYS_EXIT = 93                => sys_exit(args[..1]);
     SYS_EXIT_GROUP = 94          => sys_exit_group(args[..1]);
     SYS_WAITID = 95              => sys_waitid(args[..5]);
     SYS_SET_TID_ADDRESS = 96     => sys_set_tid_address(args[..1]);
+    SYS_UNSHARE = 97             => sys_unshare(args[..1]);
     SYS_FUTEX = 98               => sys_futex(args[..6]);
     SYS_SET_ROBUST_LIST = 99     => sys_set_robust_list(args[..2]);
+    SYS_GET_ROBUST_LIST = 100    => sys_get_robust_list(args[..3]);
     SYS_NANOSLEEP = 101          => sys_nanosleep(args[..2]);
     SYS_GETITIMER = 102          => sys_getitimer(args[..2]);
     SYS_SETITIMER = 103          => sys_setitimer(args[..3]);
     SYS_TIMER_CREATE = 107       => sys_timer_create(args[..3]);
+    SYS_TIMER_GETTIME = 108      => sys_timer_gettime(args[..2]);
+    SYS_TIMER_SETTIME = 110      => sys_timer_settime(args[..4]);
     SYS_TIMER_DELETE = 111       => sys_timer_delete(args[..1]);
+    SYS_CLOCK_SETTIME = 112      => sys_clock_settime(args[..2]);
+    SYS_CLOCK_GETTIME = 113      => sys_clock_gettime(args[..2]);
+    SYS_CLOCK_GETRES = 114       => sys_clock_getres(args[..2]);
+    SYS_CLOCK_NANOSLEEP = 115    => sys_clock_nanosleep(args[..4]);
+    SYS_SYSLOG = 116             => sys_syslog(args[..3]);
+    SYS_PTRACE = 117             => sys_ptrace(args[..4]);
     SYS_SCHED_SETPARAM = 118     => sys_sched_setparam(args[..2]);
     SYS_SCHED_SETSCHEDULER = 119 => sys_sched_setscheduler(args[..3]);
     SYS_SCHED_GETSCHEDULER = 120 => sys_sched_getscheduler(args[..1]);
@@ -218,14 +258,17 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_SCHED_GET_PRIORITY_MAX = 125 => sys_sched_get_priority_max(args[..1]);
     SYS_SCHED_GET_PRIORITY_MIN = 126 => sys_sched_get_priority_min(args[..1]);
     SYS_KILL = 129               => sys_kill(args[..2]);
+    SYS_TKILL = 130              => sys_tkill(args[..2]);
     SYS_TGKILL = 131             => sys_tgkill(args[..3]);
     SYS_SIGALTSTACK = 132        => sys_sigaltstack(args[..2]);
     SYS_RT_SIGSUSPEND = 133      => sys_rt_sigsuspend(args[..2]);
     SYS_RT_SIGACTION = 134       => sys_rt_sigaction(args[..4]);
     SYS_RT_SIGPROCMASK = 135     => sys_rt_sigprocmask(args[..4]);
     SYS_RT_SIGPENDING = 136      => sys_rt_sigpending(args[..2]);
+    SYS_RT_SIGQUEUEINFO = 138    => sys_rt_sigqueueinfo(args[..3]);
     SYS_SET_PRIORITY = 140       => sys_set_priority(args[..3]);
     SYS_GET_PRIORITY = 141       => sys_get_priority(args[..2]);
+    SYS_REBOOT = 142             => sys_reboot(args[..4]);
     SYS_SETREGID = 143           => sys_setregid(args[..2]);
     SYS_SETGID = 144             => sys_setgid(args[..1]);
     SYS_SETREUID = 145           => sys_setreuid(args[..2]);
@@ -243,6 +286,8 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_GETGROUPS = 158          => sys_getgroups(args[..2]);
     SYS_SETGROUPS = 159          => sys_setgroups(args[..2]);
     SYS_NEWUNAME = 160           => sys_uname(args[..1]);
+    SYS_SETHOSTNAME = 161        => sys_sethostname(args[..2]);
+    SYS_SETDOMAINNAME = 162      => sys_setdomainname(args[..2]);
     SYS_GETRLIMIT = 163          => sys_getrlimit(args[..2]);
     SYS_SETRLIMIT = 164          => sys_setrlimit(args[..2]);
     SYS_GETRUSAGE = 165          => sys_getrusage(args[..2]);
@@ -250,6 +295,8 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_PRCTL = 167              => sys_prctl(args[..5]);
     SYS_GETCPU = 168             => sys_getcpu(args[..3]);
     SYS_GETTIMEOFDAY = 169       => sys_gettimeofday(args[..1]);
+    SYS_SETTIMEOFDAY = 170       => sys_settimeofday(args[..2]);
+    SYS_ADJTIMEX = 171           => sys_adjtimex(args[..1]);
     SYS_GETPID = 172             => sys_getpid(args[..0]);
     SYS_GETPPID = 173            => sys_getppid(args[..0]);
     SYS_GETUID = 174             => sys_getuid(args[..0]);
@@ -259,7 +306,12 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_GETTID = 178             => sys_gettid(args[..0]);
     SYS_SEMGET = 190             => sys_semget(args[..3]);
     SYS_SEMCTL = 191             => sys_semctl(args[..4]);
+    SYS_SEMTIMEDOP = 192         => sys_semtimedop(args[..4]);
     SYS_SEMOP = 193              => sys_semop(args[..3]);
+    SYS_SHMGET = 194             => sys_shmget(args[..3]);
+    SYS_SHMCTL = 195             => sys_shmctl(args[..3]);
+    SYS_SHMAT = 196              => sys_shmat(args[..3]);
+    SYS_SHMDT = 197              => sys_shmdt(args[..1]);
     SYS_SOCKET = 198             => sys_socket(args[..3]);
     SYS_SOCKETPAIR = 199         => sys_socketpair(args[..4]);
     SYS_BIND = 200               => sys_bind(args[..3]);
@@ -277,30 +329,33 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_RECVMSG = 212            => sys_recvmsg(args[..3]);
     SYS_BRK = 214                => sys_brk(args[..1]);
     SYS_MUNMAP = 215             => sys_munmap(args[..2]);
+    SYS_ADD_KEY = 217            => sys_add_key(args[..5]);
     SYS_CLONE = 220              => sys_clone(args[..5], &user_ctx);
     SYS_EXECVE = 221             => sys_execve(args[..3], &mut user_ctx);
     SYS_MMAP = 222               => sys_mmap(args[..6]);
+    SYS_FADVISE64 = 223          => sys_fadvise64(args[..4]);
     SYS_MPROTECT = 226           => sys_mprotect(args[..3]);
     SYS_MSYNC = 227              => sys_msync(args[..3]);
     SYS_MADVISE = 233            => sys_madvise(args[..3]);
+    SYS_RT_TGSIGQUEUEINFO = 240  => sys_rt_tgsigqueueinfo(args[..4]);
     SYS_ACCEPT4 = 242            => sys_accept4(args[..4]);
     SYS_WAIT4 = 260              => sys_wait4(args[..4]);
     SYS_PRLIMIT64 = 261          => sys_prlimit64(args[..4]);
+    SYS_CLOCK_ADJTIME = 266      => sys_clock_adjtime(args[..2]);
+    SYS_KCMP = 272               => sys_kcmp(args[..5]);
     SYS_SCHED_SETATTR = 274      => sys_sched_setattr(args[..3]);
     SYS_SCHED_GETATTR = 275      => sys_sched_getattr(args[..4]);
+    SYS_SECCOMP = 277            => sys_seccomp(args[..3]);
     SYS_GETRANDOM = 278          => sys_getrandom(args[..3]);
+    SYS_BPF = 280                => sys_bpf(args[..3]);
     SYS_EXECVEAT = 281           => sys_execveat(args[..5], &mut user_ctx);
     SYS_PREADV2 = 286            => sys_preadv2(args[..5]);
     SYS_PWRITEV2 = 287           => sys_pwritev2(args[..5]);
     SYS_STATX = 291              => sys_statx(args[..5]);
-    SYS_CLOCK_GETTIME = 403      => sys_clock_gettime(args[..2]);
-    SYS_CLOCK_NANOSLEEP = 407    => sys_clock_nanosleep(args[..4]);
-    SYS_TIMER_GETTIME = 408      => sys_timer_gettime(args[..2]);
-    SYS_TIMER_SETTIME = 409      => sys_timer_settime(args[..4]);
-    SYS_TIMERFD_GETTIME = 410    => sys_timerfd_gettime(args[..2]);
-    SYS_TIMERFD_SETTIME = 411    => sys_timerfd_settime(args[..4]);
-    SYS_UTIMENSAT = 412          => sys_utimensat(args[..4]);
-    SYS_SEMTIMEDOP = 420         => sys_semtimedop(args[..4]);
+    SYS_IO_URING_SETUP = 425     => sys_io_uring_setup(args[..2]);
+    SYS_IO_URING_ENTER = 426     => sys_io_uring_enter(args[..6]);
+    SYS_IO_URING_REGISTER = 427  => sys_io_uring_register(args[..4]);
     SYS_CLONE3 = 435             => sys_clone3(args[..2], &user_ctx);
+    SYS_CLOSE_RANGE = 436        => sys_close_range(args[..3]);
     SYS_FACCESSAT2 = 439         => sys_faccessat2(args[..4]);
 }
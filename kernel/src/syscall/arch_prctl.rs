@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use ostd::cpu::context::UserContext;
+use ostd::{cpu::context::UserContext, mm::MAX_USERSPACE_VADDR};
 
 use super::SyscallReturn;
 use crate::prelude::*;
@@ -26,26 +26,44 @@ pub fn sys_arch_prctl(
         "arch_prctl_code: {:?}, addr = 0x{:x}",
         arch_prctl_code, addr
     );
-    let res = do_arch_prctl(arch_prctl_code, addr, ctx, user_ctx).unwrap();
-    Ok(SyscallReturn::Return(res as _))
+    do_arch_prctl(arch_prctl_code, addr as Vaddr, ctx, user_ctx)?;
+    Ok(SyscallReturn::Return(0))
 }
 
 pub fn do_arch_prctl(
     code: ArchPrctlCode,
-    addr: u64,
+    addr: Vaddr,
     ctx: &Context,
     user_ctx: &mut UserContext,
-) -> Result<u64> {
+) -> Result<()> {
     match code {
         ArchPrctlCode::ARCH_SET_FS => {
-            ctx.task.set_tls_pointer(addr as usize);
-            user_ctx.set_tls_pointer(addr as usize);
+            check_base_addr(addr)?;
+            user_ctx.set_tls_pointer(addr);
             user_ctx.activate_tls_pointer();
-            Ok(0)
         }
-        ArchPrctlCode::ARCH_GET_FS => Ok(user_ctx.tls_pointer() as u64),
-        ArchPrctlCode::ARCH_GET_GS | ArchPrctlCode::ARCH_SET_GS => {
-            return_errno_with_message!(Errno::EINVAL, "GS cannot be accessed from the user space")
+        ArchPrctlCode::ARCH_SET_GS => {
+            check_base_addr(addr)?;
+            user_ctx.set_gsbase(addr);
+            user_ctx.activate_gsbase();
+        }
+        ArchPrctlCode::ARCH_GET_FS => {
+            ctx.user_space().write_val(addr, &user_ctx.tls_pointer())?;
         }
+        ArchPrctlCode::ARCH_GET_GS => {
+            ctx.user_space().write_val(addr, &user_ctx.gsbase())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn check_base_addr(addr: Vaddr) -> Result<()> {
+    // Linux refuses to set a base address beyond the user space. Reference:
+    // <https://elixir.bootlin.com/linux/v6.13/source/arch/x86/kernel/process_64.c>
+    if addr >= MAX_USERSPACE_VADDR {
+        return_errno_with_message!(Errno::EPERM, "the base address is not in the user space");
     }
+
+    Ok(())
 }
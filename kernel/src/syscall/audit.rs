@@ -0,0 +1,66 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The audit mode of the missing system calls.
+//!
+//! If the kernel command line contains `syscall.audit`, every unimplemented system call is
+//! reported with a line like the following, besides failing with `ENOSYS`:
+//!
+//! ```text
+//! [missing-syscall] nr=441 pid=42 args=0x3,0x7ffd1000,0x0,0x0,0x0,0x0 comm=ls
+//! ```
+//!
+//! The same system call made by the same program is only reported once, so that the programs that
+//! retry or probe for the system calls do not flood the console. OSDK collects the reports from
+//! the output of the kernel and summarizes them after the kernel exits.
+
+use alloc::collections::btree_set::BTreeSet;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::prelude::*;
+
+crate::kernel_param! {
+    /// Reports each unimplemented system call on the console.
+    static AUDIT: bool = ("syscall.audit", false);
+}
+
+static IS_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The system call numbers and the names of the programs that have been reported.
+static REPORTED: SpinLock<BTreeSet<(u64, String)>> = SpinLock::new(BTreeSet::new());
+
+pub(super) fn init() {
+    IS_ENABLED.store(AUDIT.get(), Ordering::Relaxed);
+}
+
+/// Reports the unimplemented system call if the audit mode is enabled.
+pub(super) fn report_missing_syscall(syscall_number: u64, args: &[u64; 6], ctx: &Context) {
+    if !IS_ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let comm = ctx
+        .posix_thread
+        .thread_name()
+        .lock()
+        .as_ref()
+        .and_then(|name| name.name().ok().flatten())
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if !REPORTED.lock().insert((syscall_number, comm.clone())) {
+        return;
+    }
+
+    // The name of the program goes last since it may contain spaces.
+    println!(
+        "[missing-syscall] nr={} pid={} args={:#x},{:#x},{:#x},{:#x},{:#x},{:#x} comm={}",
+        syscall_number,
+        ctx.process.pid(),
+        args[0],
+        args[1],
+        args[2],
+        args[3],
+        args[4],
+        args[5],
+        comm
+    );
+}
@@ -0,0 +1,262 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    bpf::{BpfInsn, BpfLinkFile, BpfMapFile, BpfProgFile, BPF_MAXINSNS},
+    fs::{
+        file_handle::FileLike,
+        file_table::{get_file_fast, FdFlags, FileDesc},
+    },
+    prelude::*,
+    process::credentials::capabilities::CapSet,
+};
+
+pub fn sys_bpf(cmd: u32, attr_addr: Vaddr, size: u32, ctx: &Context) -> Result<SyscallReturn> {
+    let cmd = BpfCmd::try_from(cmd)
+        .map_err(|_| Error::with_message(Errno::EINVAL, "the BPF command is not supported"))?;
+    debug!(
+        "cmd = {:?}, attr_addr = 0x{:x}, size = {}",
+        cmd, attr_addr, size
+    );
+
+    // Like Linux with the unprivileged BPF disabled, all the commands require privileges.
+    let credentials = ctx.posix_thread.credentials();
+    if !credentials.has_capability(CapSet::BPF) && !credentials.has_capability(CapSet::SYS_ADMIN) {
+        return_errno_with_message!(Errno::EPERM, "BPF requires the CAP_BPF capability");
+    }
+
+    let ret = match cmd {
+        BpfCmd::MapCreate => map_create(read_attr(attr_addr, size, ctx)?, ctx)?,
+        BpfCmd::MapLookupElem => map_lookup_elem(read_attr(attr_addr, size, ctx)?, ctx)?,
+        BpfCmd::MapUpdateElem => map_update_elem(read_attr(attr_addr, size, ctx)?, ctx)?,
+        BpfCmd::MapDeleteElem => map_delete_elem(read_attr(attr_addr, size, ctx)?, ctx)?,
+        BpfCmd::MapGetNextKey => map_get_next_key(read_attr(attr_addr, size, ctx)?, ctx)?,
+        BpfCmd::ProgLoad => prog_load(read_attr(attr_addr, size, ctx)?, ctx)?,
+        BpfCmd::RawTracepointOpen => raw_tracepoint_open(read_attr(attr_addr, size, ctx)?, ctx)?,
+    };
+
+    Ok(SyscallReturn::Return(ret as _))
+}
+
+/// The commands of `bpf`.
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[repr(u32)]
+enum BpfCmd {
+    MapCreate = 0,
+    MapLookupElem = 1,
+    MapUpdateElem = 2,
+    MapDeleteElem = 3,
+    MapGetNextKey = 4,
+    ProgLoad = 5,
+    RawTracepointOpen = 17,
+}
+
+/// The attributes of `BPF_MAP_CREATE`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct MapCreateAttr {
+    map_type: u32,
+    key_size: u32,
+    value_size: u32,
+    max_entries: u32,
+    map_flags: u32,
+}
+
+/// The attributes of the commands on map elements.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct MapElemAttr {
+    map_fd: u32,
+    _pad: u32,
+    key: u64,
+    /// The value, or the next key for `BPF_MAP_GET_NEXT_KEY`.
+    value: u64,
+    flags: u64,
+}
+
+/// The attributes of `BPF_PROG_LOAD`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct ProgLoadAttr {
+    prog_type: u32,
+    insn_cnt: u32,
+    insns: u64,
+    license: u64,
+    log_level: u32,
+    log_size: u32,
+    log_buf: u64,
+    kern_version: u32,
+    prog_flags: u32,
+}
+
+/// The attributes of `BPF_RAW_TRACEPOINT_OPEN`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct RawTracepointAttr {
+    name: u64,
+    prog_fd: u32,
+    _pad: u32,
+}
+
+/// The maximum length of the tracepoint names.
+const MAX_TRACEPOINT_NAME_LEN: usize = 128;
+
+/// Reads the attributes of the size from the user space.
+///
+/// `union bpf_attr` in Linux is much larger than the attributes of each command. Only the
+/// leading bytes of the attributes are read, and the missing bytes are zero.
+fn read_attr<T: Pod>(addr: Vaddr, size: u32, ctx: &Context) -> Result<T> {
+    if size as usize > PAGE_SIZE {
+        return_errno_with_message!(Errno::E2BIG, "the BPF attributes are too large");
+    }
+
+    let mut attr = T::new_zeroed();
+    let len = (size as usize).min(size_of::<T>());
+    ctx.user_space()
+        .read_bytes(addr, &mut VmWriter::from(&mut attr.as_bytes_mut()[..len]))?;
+
+    Ok(attr)
+}
+
+fn map_create(attr: MapCreateAttr, ctx: &Context) -> Result<FileDesc> {
+    if attr.map_flags != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the BPF map flags are not supported");
+    }
+
+    let map_file = BpfMapFile::new(
+        attr.map_type,
+        attr.key_size,
+        attr.value_size,
+        attr.max_entries,
+    )?;
+    install_file(Arc::new(map_file), ctx)
+}
+
+fn map_lookup_elem(attr: MapElemAttr, ctx: &Context) -> Result<FileDesc> {
+    if attr.flags != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the lookup flags are not supported");
+    }
+
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, attr.map_fd as FileDesc);
+    let map_file = downcast_map(&file)?;
+
+    let key = read_buf(attr.key as Vaddr, map_file.key_size(), ctx)?;
+    let value = map_file.lookup(&key)?;
+    write_buf(attr.value as Vaddr, &value, ctx)?;
+
+    Ok(0)
+}
+
+fn map_update_elem(attr: MapElemAttr, ctx: &Context) -> Result<FileDesc> {
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, attr.map_fd as FileDesc);
+    let map_file = downcast_map(&file)?;
+
+    let key = read_buf(attr.key as Vaddr, map_file.key_size(), ctx)?;
+    let value = read_buf(attr.value as Vaddr, map_file.value_size(), ctx)?;
+    map_file.update(&key, &value, attr.flags)?;
+
+    Ok(0)
+}
+
+fn map_delete_elem(attr: MapElemAttr, ctx: &Context) -> Result<FileDesc> {
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, attr.map_fd as FileDesc);
+    let map_file = downcast_map(&file)?;
+
+    let key = read_buf(attr.key as Vaddr, map_file.key_size(), ctx)?;
+    map_file.delete(&key)?;
+
+    Ok(0)
+}
+
+fn map_get_next_key(attr: MapElemAttr, ctx: &Context) -> Result<FileDesc> {
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, attr.map_fd as FileDesc);
+    let map_file = downcast_map(&file)?;
+
+    // A null key asks for the first key.
+    let key = if attr.key == 0 {
+        None
+    } else {
+        Some(read_buf(attr.key as Vaddr, map_file.key_size(), ctx)?)
+    };
+    let next_key = map_file.next_key(key.as_deref())?;
+    write_buf(attr.value as Vaddr, &next_key, ctx)?;
+
+    Ok(0)
+}
+
+fn prog_load(attr: ProgLoadAttr, ctx: &Context) -> Result<FileDesc> {
+    if attr.prog_flags != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the BPF program flags are not supported");
+    }
+    if attr.insn_cnt == 0 || attr.insn_cnt as usize > BPF_MAXINSNS {
+        return_errno_with_message!(Errno::E2BIG, "the BPF program is empty or too long");
+    }
+    // TODO: Write the verifier log to `log_buf`.
+
+    let user_space = ctx.user_space();
+    let insns = (0..attr.insn_cnt as usize)
+        .map(|index| {
+            user_space.read_val::<BpfInsn>(attr.insns as Vaddr + index * size_of::<BpfInsn>())
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let prog_file = {
+        let mut file_table = ctx.thread_local.borrow_file_table_mut();
+        BpfProgFile::new(attr.prog_type, &insns, |fd| {
+            Ok(get_file_fast!(&mut file_table, fd).into_owned())
+        })?
+    };
+    install_file(Arc::new(prog_file), ctx)
+}
+
+fn raw_tracepoint_open(attr: RawTracepointAttr, ctx: &Context) -> Result<FileDesc> {
+    if attr.name == 0 {
+        return_errno_with_message!(Errno::EINVAL, "the tracepoint name is not specified");
+    }
+    let name = ctx
+        .user_space()
+        .read_cstring(attr.name as Vaddr, MAX_TRACEPOINT_NAME_LEN)?;
+    let name = name
+        .to_str()
+        .map_err(|_| Error::with_message(Errno::EINVAL, "the tracepoint name is invalid"))?;
+
+    let link_file = {
+        let mut file_table = ctx.thread_local.borrow_file_table_mut();
+        let file = get_file_fast!(&mut file_table, attr.prog_fd as FileDesc);
+        let Some(prog_file) = file.downcast_ref::<BpfProgFile>() else {
+            return_errno_with_message!(Errno::EINVAL, "the file is not a BPF program");
+        };
+        BpfLinkFile::new_raw_tracepoint(name, prog_file)?
+    };
+    install_file(Arc::new(link_file), ctx)
+}
+
+fn downcast_map(file: &dyn FileLike) -> Result<&BpfMapFile> {
+    file.downcast_ref::<BpfMapFile>()
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the file is not a BPF map"))
+}
+
+fn read_buf(addr: Vaddr, len: usize, ctx: &Context) -> Result<Vec<u8>> {
+    let mut buf = vec![0; len];
+    ctx.user_space()
+        .read_bytes(addr, &mut VmWriter::from(buf.as_mut_slice()))?;
+    Ok(buf)
+}
+
+fn write_buf(addr: Vaddr, buf: &[u8], ctx: &Context) -> Result<()> {
+    ctx.user_space()
+        .write_bytes(addr, &mut VmReader::from(buf))?;
+    Ok(())
+}
+
+/// Installs the BPF file in the file table and returns its file descriptor.
+///
+/// Like Linux, the file descriptors of the BPF objects are close-on-exec.
+fn install_file(file: Arc<dyn FileLike>, ctx: &Context) -> Result<FileDesc> {
+    let file_table = ctx.thread_local.borrow_file_table();
+    file_table.unwrap().write().insert(file, FdFlags::CLOEXEC)
+}
@@ -37,14 +37,21 @@ pub fn sys_capget(
 
     // Annoying legacy format with 64-bit capabilities exposed as two sets of 32-bit fields,
     // so we need to split the capability values up.
-    let result = cap_user_data_t {
-        // Note we silently drop the upper capabilities here.
-        // This behavior is considered fail-safe behavior.
+    let result_low = cap_user_data_t {
         effective: effective_capset.as_u32(),
         permitted: permitted_capset.as_u32(),
         inheritable: inheritable_capset.as_u32(),
     };
+    let result_high = cap_user_data_t {
+        effective: (effective_capset.bits() >> 32) as u32,
+        permitted: (permitted_capset.bits() >> 32) as u32,
+        inheritable: (inheritable_capset.bits() >> 32) as u32,
+    };
 
-    user_space.write_val(cap_user_data_addr, &result)?;
+    user_space.write_val(cap_user_data_addr, &result_low)?;
+    user_space.write_val(
+        cap_user_data_addr + size_of::<cap_user_data_t>(),
+        &result_high,
+    )?;
     Ok(SyscallReturn::Return(0))
 }
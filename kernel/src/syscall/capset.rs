@@ -33,28 +33,51 @@ pub fn sys_capset(
         return_errno_with_message!(Errno::EINVAL, "invalid pid");
     }
 
-    // Check if the current process has CAP_SET_CAP capability
-    if !(ctx
-        .posix_thread
-        .credentials()
-        .permitted_capset()
-        .contains(CapSet::SETPCAP))
-    {
-        return_errno_with_message!(Errno::EPERM, "permission denied");
-    }
-
-    // Convert the cap(u32) to u64
-    let cap_user_data: cap_user_data_t =
+    // Convert the two sets of 32-bit capabilities to u64
+    let cap_user_data_low: cap_user_data_t =
         user_space.read_val::<cap_user_data_t>(cap_user_data_addr)?;
-    let inheritable = make_kernel_cap(cap_user_data.inheritable, 0);
-    let permitted = make_kernel_cap(cap_user_data.permitted, 0);
-    let effective = make_kernel_cap(cap_user_data.effective, 0);
+    let cap_user_data_high: cap_user_data_t = user_space
+        .read_val::<cap_user_data_t>(cap_user_data_addr + size_of::<cap_user_data_t>())?;
+    let inheritable = CapSet::from_bits_truncate(make_kernel_cap(
+        cap_user_data_low.inheritable,
+        cap_user_data_high.inheritable,
+    ));
+    let permitted = CapSet::from_bits_truncate(make_kernel_cap(
+        cap_user_data_low.permitted,
+        cap_user_data_high.permitted,
+    ));
+    let effective = CapSet::from_bits_truncate(make_kernel_cap(
+        cap_user_data_low.effective,
+        cap_user_data_high.effective,
+    ));
 
     let credentials = ctx.posix_thread.credentials_mut();
 
-    credentials.set_inheritable_capset(CapSet::from_bits_truncate(inheritable));
-    credentials.set_permitted_capset(CapSet::from_bits_truncate(permitted));
-    credentials.set_effective_capset(CapSet::from_bits_truncate(effective));
+    // The capabilities can only be dropped, except that the inheritable capabilities can be
+    // raised up to the permitted capabilities, or to any capabilities with `CAP_SETPCAP`.
+    // See: https://elixir.bootlin.com/linux/v6.9.3/source/security/commoncap.c#L254 for more details.
+    if !credentials.permitted_capset().contains(permitted) {
+        return_errno_with_message!(Errno::EPERM, "the permitted capabilities cannot be raised");
+    }
+    if !permitted.contains(effective) {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "the effective capabilities must be a subset of the permitted capabilities"
+        );
+    }
+    if !credentials.has_capability(CapSet::SETPCAP)
+        && !(credentials.inheritable_capset() | credentials.permitted_capset())
+            .contains(inheritable)
+    {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "the inheritable capabilities cannot be raised beyond the permitted capabilities"
+        );
+    }
+
+    credentials.set_inheritable_capset(inheritable);
+    credentials.set_permitted_capset(permitted);
+    credentials.set_effective_capset(effective);
 
     Ok(SyscallReturn::Return(0))
 }
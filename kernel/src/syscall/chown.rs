@@ -8,20 +8,22 @@ use crate::{
         utils::PATH_MAX,
     },
     prelude::*,
-    process::{Gid, Uid},
+    process::{credentials::capabilities::CapSet, Gid, Uid},
 };
 
 pub fn sys_fchown(fd: FileDesc, uid: i32, gid: i32, ctx: &Context) -> Result<SyscallReturn> {
     debug!("fd = {}, uid = {}, gid = {}", fd, uid, gid);
 
-    let uid = to_optional_id(uid, Uid::new)?;
-    let gid = to_optional_id(gid, Gid::new)?;
+    let user_ns = ctx.posix_thread.credentials().user_ns();
+    let uid = to_optional_id(uid, |uid| user_ns.uid_to_kernel(uid))?;
+    let gid = to_optional_id(gid, |gid| user_ns.gid_to_kernel(gid))?;
     if uid.is_none() && gid.is_none() {
         return Ok(SyscallReturn::Return(0));
     }
 
     let mut file_table = ctx.thread_local.borrow_file_table_mut();
     let file = get_file_fast!(&mut file_table, fd);
+    check_chown_perm(file.owner()?, file.group()?, uid, gid, ctx)?;
     if let Some(uid) = uid {
         file.set_owner(uid)?;
     }
@@ -69,8 +71,9 @@ pub fn sys_fchownat(
         return self::sys_fchown(dirfd, uid, gid, ctx);
     }
 
-    let uid = to_optional_id(uid, Uid::new)?;
-    let gid = to_optional_id(gid, Gid::new)?;
+    let user_ns = ctx.posix_thread.credentials().user_ns();
+    let uid = to_optional_id(uid, |uid| user_ns.uid_to_kernel(uid))?;
+    let gid = to_optional_id(gid, |gid| user_ns.gid_to_kernel(gid))?;
     if uid.is_none() && gid.is_none() {
         return Ok(SyscallReturn::Return(0));
     }
@@ -78,13 +81,15 @@ pub fn sys_fchownat(
     let dentry = {
         let path = path.to_string_lossy();
         let fs_path = FsPath::new(dirfd, path.as_ref())?;
-        let fs = ctx.posix_thread.fs().resolver().read();
+        let fs_ref = ctx.posix_thread.fs().read();
+        let fs = fs_ref.resolver().read();
         if flags.contains(ChownFlags::AT_SYMLINK_NOFOLLOW) {
             fs.lookup_no_follow(&fs_path)?
         } else {
             fs.lookup(&fs_path)?
         }
     };
+    check_chown_perm(dentry.owner()?, dentry.group()?, uid, gid, ctx)?;
     if let Some(uid) = uid {
         dentry.set_owner(uid)?;
     }
@@ -94,9 +99,45 @@ pub fn sys_fchownat(
     Ok(SyscallReturn::Return(0))
 }
 
-fn to_optional_id<T>(id: i32, f: impl Fn(u32) -> T) -> Result<Option<T>> {
+/// Checks whether the current thread can change the owner and the group of the file.
+///
+/// Without the `CAP_CHOWN` capability, only the owner of the file can "change" the owner to
+/// itself, or change the group to one of its groups. The capability is only in effect if the
+/// owner and the group of the file are mapped in the user namespace.
+fn check_chown_perm(
+    owner: Uid,
+    group: Gid,
+    new_owner: Option<Uid>,
+    new_group: Option<Gid>,
+    ctx: &Context,
+) -> Result<()> {
+    let credentials = ctx.posix_thread.credentials();
+    if credentials.has_capability_for_owner(CapSet::CHOWN, owner, group) {
+        return Ok(());
+    }
+
+    let is_owner = credentials.fsuid() == owner;
+    if new_owner.is_some_and(|new_owner| !is_owner || new_owner != owner) {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "changing the owner requires the CAP_CHOWN capability"
+        );
+    }
+    if new_group.is_some_and(|new_group| {
+        !is_owner || (new_group != group && !credentials.is_in_group(new_group))
+    }) {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "changing the group to a group that the owner is not in requires the CAP_CHOWN capability"
+        );
+    }
+
+    Ok(())
+}
+
+fn to_optional_id<T>(id: i32, f: impl Fn(u32) -> Result<T>) -> Result<Option<T>> {
     let id = if id >= 0 {
-        Some(f(id as u32))
+        Some(f(id as u32)?)
     } else if id == -1 {
         // If the owner or group is specified as -1, then that ID is not changed.
         None
@@ -3,6 +3,7 @@
 use core::time::Duration;
 
 use int_to_c_enum::TryFromInt;
+use ostd::timer::Jiffies;
 
 use super::SyscallReturn;
 use crate::{
@@ -36,6 +37,23 @@ pub fn sys_clock_gettime(
     Ok(SyscallReturn::Return(0))
 }
 
+pub fn sys_clock_getres(
+    clockid: clockid_t,
+    timespec_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!("clockid = {:?}", clockid);
+
+    let resolution = clock_resolution(clockid)?;
+
+    if timespec_addr != 0 {
+        let timespec = timespec_t::from(resolution);
+        ctx.user_space().write_val(timespec_addr, &timespec)?;
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
+
 // The hard-coded clock IDs.
 #[derive(Debug, Copy, Clone, TryFromInt, PartialEq)]
 #[repr(i32)]
@@ -114,16 +132,18 @@ pub enum DynamicClockType {
 pub fn read_clock(clockid: clockid_t, ctx: &Context) -> Result<Duration> {
     if clockid >= 0 {
         let clock_id = ClockId::try_from(clockid)?;
-        match clock_id {
-            ClockId::CLOCK_REALTIME => Ok(RealTimeClock::get().read_time()),
-            ClockId::CLOCK_MONOTONIC => Ok(MonotonicClock::get().read_time()),
-            ClockId::CLOCK_MONOTONIC_RAW => Ok(MonotonicRawClock::get().read_time()),
-            ClockId::CLOCK_REALTIME_COARSE => Ok(RealTimeCoarseClock::get().read_time()),
-            ClockId::CLOCK_MONOTONIC_COARSE => Ok(MonotonicCoarseClock::get().read_time()),
-            ClockId::CLOCK_BOOTTIME => Ok(BootTimeClock::get().read_time()),
-            ClockId::CLOCK_PROCESS_CPUTIME_ID => Ok(ctx.process.prof_clock().read_time()),
-            ClockId::CLOCK_THREAD_CPUTIME_ID => Ok(ctx.posix_thread.prof_clock().read_time()),
-        }
+        let time = match clock_id {
+            ClockId::CLOCK_REALTIME => RealTimeClock::get().read_time(),
+            ClockId::CLOCK_MONOTONIC => MonotonicClock::get().read_time(),
+            ClockId::CLOCK_MONOTONIC_RAW => MonotonicRawClock::get().read_time(),
+            ClockId::CLOCK_REALTIME_COARSE => RealTimeCoarseClock::get().read_time(),
+            ClockId::CLOCK_MONOTONIC_COARSE => MonotonicCoarseClock::get().read_time(),
+            ClockId::CLOCK_BOOTTIME => BootTimeClock::get().read_time(),
+            ClockId::CLOCK_PROCESS_CPUTIME_ID => ctx.process.prof_clock().read_time(),
+            ClockId::CLOCK_THREAD_CPUTIME_ID => ctx.posix_thread.prof_clock().read_time(),
+        };
+        // The monotonic clocks and the boot-time clock are shifted in the time namespace.
+        Ok(ctx.process.time_ns().host_to_ns(clockid, time))
     } else {
         let dynamic_clockid_info = DynamicClockIdInfo::try_from(clockid)?;
         match dynamic_clockid_info {
@@ -131,10 +151,13 @@ pub fn read_clock(clockid: clockid_t, ctx: &Context) -> Result<Duration> {
                 let process = process_table::get_process(pid)
                     .ok_or_else(|| crate::Error::with_message(Errno::EINVAL, "invalid clock ID"))?;
                 match clock_type {
-                    DynamicClockType::Profiling => Ok(process.prof_clock().read_time()),
+                    // The scheduling clock measures the CPU time like the profiling clock, but
+                    // the CPU time is only accounted at the granularity of ticks.
+                    DynamicClockType::Profiling | DynamicClockType::Scheduling => {
+                        Ok(process.prof_clock().read_time())
+                    }
                     DynamicClockType::Virtual => Ok(process.prof_clock().user_clock().read_time()),
-                    // TODO: support scheduling clock and fd clock.
-                    _ => unimplemented!(),
+                    DynamicClockType::FD => unreachable!(),
                 }
             }
             DynamicClockIdInfo::Tid(tid, clock_type) => {
@@ -142,14 +165,59 @@ pub fn read_clock(clockid: clockid_t, ctx: &Context) -> Result<Duration> {
                     .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid clock ID"))?;
                 let posix_thread = thread.as_posix_thread().unwrap();
                 match clock_type {
-                    DynamicClockType::Profiling => Ok(posix_thread.prof_clock().read_time()),
+                    DynamicClockType::Profiling | DynamicClockType::Scheduling => {
+                        Ok(posix_thread.prof_clock().read_time())
+                    }
                     DynamicClockType::Virtual => {
                         Ok(posix_thread.prof_clock().user_clock().read_time())
                     }
-                    _ => unimplemented!(),
+                    DynamicClockType::FD => unreachable!(),
                 }
             }
-            DynamicClockIdInfo::Fd(_) => unimplemented!(),
+            // TODO: Support the clocks of character devices, e.g., PTP hardware clocks.
+            DynamicClockIdInfo::Fd(_) => {
+                return_errno_with_message!(Errno::EINVAL, "FD clocks are not supported")
+            }
+        }
+    }
+}
+
+/// Returns the resolution of a clock specified by the input clock ID.
+///
+/// The coarse clocks and the CPU-time clocks are updated at each tick, so their resolution is one
+/// jiffy. The other clocks are backed by the high-resolution clock source.
+fn clock_resolution(clockid: clockid_t) -> Result<Duration> {
+    const HIGH_RESOLUTION: Duration = Duration::from_nanos(1);
+    let tick_resolution = Jiffies::new(1).as_duration();
+
+    if clockid >= 0 {
+        let clock_id = ClockId::try_from(clockid)?;
+        match clock_id {
+            ClockId::CLOCK_REALTIME
+            | ClockId::CLOCK_MONOTONIC
+            | ClockId::CLOCK_MONOTONIC_RAW
+            | ClockId::CLOCK_BOOTTIME => Ok(HIGH_RESOLUTION),
+            ClockId::CLOCK_REALTIME_COARSE
+            | ClockId::CLOCK_MONOTONIC_COARSE
+            | ClockId::CLOCK_PROCESS_CPUTIME_ID
+            | ClockId::CLOCK_THREAD_CPUTIME_ID => Ok(tick_resolution),
+        }
+    } else {
+        match DynamicClockIdInfo::try_from(clockid)? {
+            DynamicClockIdInfo::Pid(pid, _) => {
+                if process_table::get_process(pid).is_none() {
+                    return_errno_with_message!(Errno::EINVAL, "invalid clock ID");
+                }
+            }
+            DynamicClockIdInfo::Tid(tid, _) => {
+                if thread_table::get_thread(tid).is_none() {
+                    return_errno_with_message!(Errno::EINVAL, "invalid clock ID");
+                }
+            }
+            DynamicClockIdInfo::Fd(_) => {
+                return_errno_with_message!(Errno::EINVAL, "FD clocks are not supported")
+            }
         }
+        Ok(tick_resolution)
     }
 }
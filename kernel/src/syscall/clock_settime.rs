@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use super::{clock_gettime::ClockId, SyscallReturn};
+use crate::{
+    prelude::*,
+    process::credentials::capabilities::CapSet,
+    time::{clockid_t, set_real_time, timespec_t},
+};
+
+pub fn sys_clock_settime(
+    clockid: clockid_t,
+    timespec_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let timespec = ctx.user_space().read_val::<timespec_t>(timespec_addr)?;
+    debug!("clockid = {:?}, timespec = {:?}", clockid, timespec);
+
+    // Only the real-time clock can be set. Other clocks, e.g., the monotonic clock, are not
+    // settable by definition.
+    if !matches!(ClockId::try_from(clockid), Ok(ClockId::CLOCK_REALTIME)) {
+        return_errno_with_message!(Errno::EINVAL, "the clock cannot be set");
+    }
+
+    let real_time = Duration::try_from(timespec)?;
+    check_sys_time_capability(ctx)?;
+    set_real_time(real_time)?;
+
+    Ok(SyscallReturn::Return(0))
+}
+
+/// Checks whether the current thread is allowed to set the system clock.
+pub(super) fn check_sys_time_capability(ctx: &Context) -> Result<()> {
+    if !ctx
+        .posix_thread
+        .credentials()
+        .has_capability(CapSet::SYS_TIME)
+    {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "setting the system clock requires CAP_SYS_TIME"
+        );
+    }
+
+    Ok(())
+}
@@ -23,7 +23,7 @@ pub fn sys_clone(
 ) -> Result<SyscallReturn> {
     let args = CloneArgs::for_clone(clone_flags, parent_tidptr, child_tidptr, tls, new_sp)?;
     debug!("flags = {:?}, child_stack_ptr = 0x{:x}, parent_tid_ptr = 0x{:x?}, child tid ptr = 0x{:x}, tls = 0x{:x}", args.flags, args.stack, args.parent_tid, args.child_tid, args.tls);
-    let child_pid = clone_child(ctx, parent_context, args).unwrap();
+    let child_pid = clone_child(ctx, parent_context, args)?;
     Ok(SyscallReturn::Return(child_pid as _))
 }
 
@@ -38,12 +38,15 @@ pub fn sys_clone3(
         clong_args_addr,
         size
     );
-    if size != core::mem::size_of::<Clone3Args>() {
-        return_errno_with_message!(Errno::EINVAL, "invalid size");
+    if size > PAGE_SIZE {
+        return_errno_with_message!(Errno::E2BIG, "the size of clone_args is too large");
+    }
+    if size < CLONE_ARGS_SIZE_VER0 {
+        return_errno_with_message!(Errno::EINVAL, "the size of clone_args is too small");
     }
 
     let clone_args = {
-        let args: Clone3Args = ctx.user_space().read_val(clong_args_addr)?;
+        let args: Clone3Args = ctx.user_space().read_struct(clong_args_addr, size)?;
         trace!("clone3 args = {:x?}", args);
         CloneArgs::from(args)
     };
@@ -54,6 +57,9 @@ pub fn sys_clone3(
     Ok(SyscallReturn::Return(child_pid as _))
 }
 
+/// The size of the first published version of `struct clone_args`.
+const CLONE_ARGS_SIZE_VER0: usize = 64;
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pod)]
 struct Clone3Args {
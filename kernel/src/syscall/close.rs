@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use ostd::sync::RwArc;
+
 use super::SyscallReturn;
 use crate::{fs::file_table::FileDesc, prelude::*};
 
@@ -29,3 +31,50 @@ pub fn sys_close(fd: FileDesc, ctx: &Context) -> Result<SyscallReturn> {
     // <https://man7.org/linux/man-pages/man2/close.2.html>.
     Ok(SyscallReturn::Return(0))
 }
+
+pub fn sys_close_range(
+    first: u32,
+    last: u32,
+    raw_flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let flags = CloseRangeFlags::from_bits(raw_flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid flags"))?;
+    debug!("first = {}, last = {}, flags = {:?}", first, last, flags);
+
+    if first > last {
+        return_errno_with_message!(Errno::EINVAL, "the range is invalid");
+    }
+    let range = first as usize..last as usize + 1;
+
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+
+    if flags.contains(CloseRangeFlags::UNSHARE) && file_table.unwrap().get().is_none() {
+        // The file table is shared with other threads, so it is cloned to be private. The files
+        // that are going to be closed are also closed in the new table only.
+        let new_file_table = RwArc::new(file_table.unwrap().read().clone());
+        *ctx.posix_thread.file_table().lock() = Some(new_file_table.clone_ro());
+        file_table.replace(new_file_table);
+    }
+
+    if flags.contains(CloseRangeFlags::CLOEXEC) {
+        file_table.unwrap().read().set_cloexec_in_range(range);
+        return Ok(SyscallReturn::Return(0));
+    }
+
+    let files = file_table.unwrap().write().close_files_in_range(range);
+    // Cleanup work needs to be done in the `Drop` impl, which should be done without holding the
+    // lock of the file table.
+    drop(files);
+
+    Ok(SyscallReturn::Return(0))
+}
+
+bitflags! {
+    struct CloseRangeFlags: u32 {
+        /// Unshares the file table before closing the file descriptors.
+        const UNSHARE = 1 << 1;
+        /// Sets the close-on-exec flag instead of closing the file descriptors.
+        const CLOEXEC = 1 << 2;
+    }
+}
@@ -2,7 +2,7 @@
 
 use super::SyscallReturn;
 use crate::{
-    fs::file_table::{get_file_fast, FdFlags, FileDesc},
+    fs::file_table::{get_file_fast, FdCreationFlags, FdFlags, FileDesc},
     prelude::*,
     process::ResourceType,
 };
@@ -37,9 +37,8 @@ pub fn sys_dup3(
 ) -> Result<SyscallReturn> {
     debug!("old_fd = {}, new_fd = {}", old_fd, new_fd);
 
-    let fdflag = match flags {
-        0x0 => FdFlags::empty(),
-        0x80000 => FdFlags::CLOEXEC,
+    let fdflag = match FdCreationFlags::from_bits(flags) {
+        Some(flags) if !flags.is_nonblocking() => flags.fd_flags(),
         _ => return_errno_with_message!(Errno::EINVAL, "flags must be O_CLOEXEC or 0"),
     };
 
@@ -7,8 +7,7 @@ use crate::{
     events::IoEvents,
     fs::{
         epoll::{EpollCtl, EpollEvent, EpollFile, EpollFlags},
-        file_table::{get_file_fast, FdFlags, FileDesc},
-        utils::CreationFlags,
+        file_table::{get_file_fast, FdCreationFlags, FileDesc},
     },
     prelude::*,
     process::signal::sig_mask::SigMask,
@@ -27,22 +26,15 @@ pub fn sys_epoll_create(size: i32, ctx: &Context) -> Result<SyscallReturn> {
 pub fn sys_epoll_create1(flags: u32, ctx: &Context) -> Result<SyscallReturn> {
     debug!("flags = 0x{:x}", flags);
 
-    let fd_flags = {
-        let flags = CreationFlags::from_bits(flags)
-            .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid flags"))?;
-        if flags == CreationFlags::empty() {
-            FdFlags::empty()
-        } else if flags == CreationFlags::O_CLOEXEC {
-            FdFlags::CLOEXEC
-        } else {
-            // Only O_CLOEXEC is valid
-            return_errno_with_message!(Errno::EINVAL, "invalid flags");
-        }
+    // Only `EPOLL_CLOEXEC` is valid.
+    let fd_flags = match FdCreationFlags::from_bits(flags) {
+        Some(flags) if !flags.is_nonblocking() => flags.fd_flags(),
+        _ => return_errno_with_message!(Errno::EINVAL, "invalid flags"),
     };
 
     let epoll_file: Arc<EpollFile> = EpollFile::new();
     let file_table = ctx.thread_local.borrow_file_table();
-    let fd = file_table.unwrap().write().insert(epoll_file, fd_flags);
+    let fd = file_table.unwrap().write().insert(epoll_file, fd_flags)?;
     Ok(SyscallReturn::Return(fd as _))
 }
 
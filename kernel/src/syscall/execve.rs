@@ -15,7 +15,7 @@ use crate::{
     },
     prelude::*,
     process::{
-        check_executable_file, posix_thread::ThreadName, renew_vm_and_map, Credentials, Process,
+        check_executable_file, posix_thread::ThreadName, ptrace, renew_vm, Credentials, Process,
         ProgramToLoad, MAX_ARGV_NUMBER, MAX_ARG_LEN, MAX_ENVP_NUMBER, MAX_ENV_LEN,
     },
 };
@@ -66,7 +66,8 @@ fn lookup_executable_file(
         let file = get_file_fast!(&mut file_table, dfd);
         file.as_inode_or_err()?.dentry().clone()
     } else {
-        let fs_resolver = ctx.posix_thread.fs().resolver().read();
+        let fs_ref = ctx.posix_thread.fs().read();
+        let fs_resolver = fs_ref.resolver().read();
         let fs_path = FsPath::new(dfd, &filename)?;
         if flags.contains(OpenFlags::AT_SYMLINK_NOFOLLOW) {
             fs_resolver.lookup_no_follow(&fs_path)?
@@ -118,13 +119,14 @@ fn do_execve(
     drop(closed_files);
 
     debug!("load program to root vmar");
-    let fs_resolver = &*posix_thread.fs().resolver().read();
+    let fs_ref = posix_thread.fs().read();
+    let fs_resolver = &*fs_ref.resolver().read();
     let program_to_load =
         ProgramToLoad::build_from_file(elf_file.clone(), fs_resolver, argv, envp, 1)?;
 
     let process_vm = process.vm();
     if process.status().is_vfork_child() {
-        renew_vm_and_map(ctx);
+        renew_vm(ctx);
 
         // Resumes the parent process.
         process.status().set_vfork_child(false);
@@ -134,21 +136,31 @@ fn do_execve(
         // FIXME: Currently, the efficiency of replacing the VMAR is lower than that
         // of directly clearing the VMAR. Therefore, if not in vfork case we will only
         // clear the VMAR.
-        process_vm.clear_and_map();
+        process_vm.clear();
     }
 
+    // The credentials are updated before loading the program, because the dynamic linker needs
+    // to know whether the program runs in the secure mode via the auxiliary vector.
+    let credentials = posix_thread.credentials_mut();
+    let no_new_privs = posix_thread.no_new_privs();
+    set_uid_from_elf(process, &credentials, &elf_file, no_new_privs)?;
+    set_gid_from_elf(process, &credentials, &elf_file, no_new_privs)?;
+    credentials.update_capabilities_for_exec();
+
     let (new_executable_path, elf_load_info) =
-        program_to_load.load_to_vm(process_vm, fs_resolver)?;
+        program_to_load.load_to_vm(process_vm, fs_resolver, &posix_thread.credentials())?;
 
     // After the program has been successfully loaded, the virtual memory of the current process
     // is initialized. Hence, it is necessary to clear the previously recorded robust list.
     *thread_local.robust_list().borrow_mut() = None;
-    debug!("load elf in execve succeeds");
 
-    let credentials = posix_thread.credentials_mut();
-    set_uid_from_elf(process, &credentials, &elf_file)?;
-    set_gid_from_elf(process, &credentials, &elf_file)?;
-    credentials.set_keep_capabilities(false);
+    // Disable the alternate signal stack, which is in the old address space.
+    *thread_local.sig_stack().borrow_mut() = None;
+
+    // The POSIX timers are not preserved across `execve`.
+    process.timer_manager().clear_posix_timers();
+
+    debug!("load elf in execve succeeds");
 
     // set executable path
     process.set_executable_path(new_executable_path);
@@ -157,6 +169,10 @@ fn do_execve(
     // set cpu context to default
     *user_context.general_regs_mut() = RawGeneralRegs::default();
     user_context.set_tls_pointer(0);
+    // The new program must not inherit the TLS pointer (and the GS base on x86) of the old one.
+    user_context.activate_tls_pointer();
+    #[cfg(target_arch = "x86_64")]
+    user_context.activate_gsbase();
     *user_context.fpu_state_mut() = FpuState::default();
     // FIXME: how to reset the FPU state correctly? Before returning to the user space,
     // the kernel will call `handle_pending_signal`, which may update the CPU states so that
@@ -169,6 +185,8 @@ fn do_execve(
     // set new user stack top
     user_context.set_stack_pointer(elf_load_info.user_stack_top() as _);
     debug!("user stack top: 0x{:x}", elf_load_info.user_stack_top());
+
+    ptrace::notify_exec(ctx, user_context);
     Ok(())
 }
 
@@ -218,12 +236,15 @@ fn read_cstring_vec(
 }
 
 /// Sets uid for credentials as the same of uid of elf file if elf file has `set_uid` bit.
+///
+/// The `set_uid` bit is ignored if `no_new_privs` is set.
 fn set_uid_from_elf(
     current: &Process,
     credentials: &Credentials<WriteOp>,
     elf_file: &Dentry,
+    no_new_privs: bool,
 ) -> Result<()> {
-    if elf_file.mode()?.has_set_uid() {
+    if !no_new_privs && elf_file.mode()?.has_set_uid() {
         let uid = elf_file.owner()?;
         credentials.set_euid(uid);
 
@@ -236,12 +257,15 @@ fn set_uid_from_elf(
 }
 
 /// Sets gid for credentials as the same of gid of elf file if elf file has `set_gid` bit.
+///
+/// The `set_gid` bit is ignored if `no_new_privs` is set.
 fn set_gid_from_elf(
     current: &Process,
     credentials: &Credentials<WriteOp>,
     elf_file: &Dentry,
+    no_new_privs: bool,
 ) -> Result<()> {
-    if elf_file.mode()?.has_set_gid() {
+    if !no_new_privs && elf_file.mode()?.has_set_gid() {
         let gid = elf_file.group()?;
         credentials.set_egid(gid);
 
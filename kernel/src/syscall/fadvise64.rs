@@ -0,0 +1,35 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    fs::{
+        file_table::{get_file_fast, FileDesc},
+        utils::FileAdvice,
+    },
+    prelude::*,
+};
+
+pub fn sys_fadvise64(
+    fd: FileDesc,
+    offset: i64,
+    len: i64,
+    advice: i32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "fd = {}, offset = {}, len = {}, advice = {}",
+        fd, offset, len, advice
+    );
+
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, fd);
+
+    let advice = FileAdvice::try_from(advice)
+        .map_err(|_| Error::with_message(Errno::EINVAL, "invalid fadvise advice"))?;
+    if offset < 0 || len < 0 {
+        return_errno_with_message!(Errno::EINVAL, "offset or len is negative");
+    }
+    file.fadvise(advice, offset as usize, len as usize)?;
+
+    Ok(SyscallReturn::Return(0))
+}
@@ -4,37 +4,99 @@ use super::SyscallReturn;
 use crate::{
     fs::{
         file_handle::FileLike,
-        file_table::{get_file_fast, FdFlags, FileDesc, WithFileTable},
+        file_table::{get_file_fast, FdFlags, FileDesc, FileOwner, OwnerId, WithFileTable},
+        pipe,
         utils::{
-            FileRange, RangeLockItem, RangeLockItemBuilder, RangeLockType, StatusFlags, OFFSET_MAX,
+            FileRange, FileSeals, RangeLockItem, RangeLockItemBuilder, RangeLockType, StatusFlags,
+            OFFSET_MAX,
         },
     },
     prelude::*,
-    process::{process_table, Pid},
+    process::{
+        credentials::capabilities::CapSet, posix_thread::thread_table, process_table, Pid,
+        ResourceType,
+    },
 };
 
 pub fn sys_fcntl(fd: FileDesc, cmd: i32, arg: u64, ctx: &Context) -> Result<SyscallReturn> {
     let fcntl_cmd = FcntlCmd::try_from(cmd)?;
     debug!("fd = {}, cmd = {:?}, arg = {}", fd, fcntl_cmd, arg);
-    match fcntl_cmd {
-        FcntlCmd::F_DUPFD => handle_dupfd(fd, arg, FdFlags::empty(), ctx),
-        FcntlCmd::F_DUPFD_CLOEXEC => handle_dupfd(fd, arg, FdFlags::CLOEXEC, ctx),
-        FcntlCmd::F_GETFD => handle_getfd(fd, ctx),
-        FcntlCmd::F_SETFD => handle_setfd(fd, arg, ctx),
-        FcntlCmd::F_GETFL => handle_getfl(fd, ctx),
-        FcntlCmd::F_SETFL => handle_setfl(fd, arg, ctx),
-        FcntlCmd::F_GETLK => handle_getlk(fd, arg, ctx),
-        FcntlCmd::F_SETLK => handle_setlk(fd, arg, true, ctx),
-        FcntlCmd::F_SETLKW => handle_setlk(fd, arg, false, ctx).map_err(|err| match err.error() {
-            Errno::EINTR => Error::new(Errno::ERESTARTSYS),
-            _ => err,
-        }),
-        FcntlCmd::F_GETOWN => handle_getown(fd, ctx),
-        FcntlCmd::F_SETOWN => handle_setown(fd, arg, ctx),
+
+    let op = fcntl_cmd.op();
+    if !op.is_path_allowed {
+        let mut file_table = ctx.thread_local.borrow_file_table_mut();
+        let file = get_file_fast!(&mut file_table, fd);
+        if file.status_flags().contains(StatusFlags::O_PATH) {
+            return_errno_with_message!(Errno::EBADF, "the command is not allowed on O_PATH files");
+        }
+    }
+
+    (op.handler)(fd, arg, ctx)
+}
+
+/// The handler of an `fcntl` command, which takes the file descriptor and the argument.
+type FcntlHandler = fn(FileDesc, u64, &Context) -> Result<SyscallReturn>;
+
+/// How an `fcntl` command is handled.
+struct FcntlOp {
+    handler: FcntlHandler,
+    /// Whether the command can be applied to files opened with `O_PATH`.
+    ///
+    /// Like Linux, only the commands that operate on the file descriptor itself are allowed.
+    is_path_allowed: bool,
+}
+
+impl FcntlCmd {
+    fn op(self) -> FcntlOp {
+        let (handler, is_path_allowed): (FcntlHandler, _) = match self {
+            Self::F_DUPFD => (handle_dupfd, true),
+            Self::F_DUPFD_CLOEXEC => (handle_dupfd_cloexec, true),
+            Self::F_GETFD => (handle_getfd, true),
+            Self::F_SETFD => (handle_setfd, true),
+            Self::F_GETFL => (handle_getfl, true),
+            Self::F_SETFL => (handle_setfl, false),
+            Self::F_GETLK => (handle_getlk, false),
+            Self::F_SETLK => (handle_setlk, false),
+            Self::F_SETLKW => (handle_setlkw, false),
+            Self::F_GETOWN => (handle_getown, false),
+            Self::F_SETOWN => (handle_setown, false),
+            Self::F_GETOWN_EX => (handle_getown_ex, false),
+            Self::F_SETOWN_EX => (handle_setown_ex, false),
+            Self::F_SETPIPE_SZ => (handle_setpipe_sz, false),
+            Self::F_GETPIPE_SZ => (handle_getpipe_sz, false),
+            Self::F_ADD_SEALS => (handle_add_seals, false),
+            Self::F_GET_SEALS => (handle_get_seals, false),
+        };
+        FcntlOp {
+            handler,
+            is_path_allowed,
+        }
     }
 }
 
-fn handle_dupfd(fd: FileDesc, arg: u64, flags: FdFlags, ctx: &Context) -> Result<SyscallReturn> {
+fn handle_dupfd(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn> {
+    dup_fd(fd, arg, FdFlags::empty(), ctx)
+}
+
+fn handle_dupfd_cloexec(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn> {
+    dup_fd(fd, arg, FdFlags::CLOEXEC, ctx)
+}
+
+fn dup_fd(fd: FileDesc, arg: u64, flags: FdFlags, ctx: &Context) -> Result<SyscallReturn> {
+    // Like Linux, the minimum file descriptor is checked against the limit before the file
+    // descriptor is allocated, so `EINVAL` is returned instead of `EMFILE`.
+    let nofile_limit = ctx
+        .process
+        .resource_limits()
+        .get_rlimit(ResourceType::RLIMIT_NOFILE)
+        .get_cur();
+    if arg >= nofile_limit || arg > FileDesc::MAX as u64 {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the minimum file descriptor exceeds the `RLIMIT_NOFILE` limit"
+        );
+    }
+
     let file_table = ctx.thread_local.borrow_file_table();
     let new_fd = file_table
         .unwrap()
@@ -43,7 +105,7 @@ fn handle_dupfd(fd: FileDesc, arg: u64, flags: FdFlags, ctx: &Context) -> Result
     Ok(SyscallReturn::Return(new_fd as _))
 }
 
-fn handle_getfd(fd: FileDesc, ctx: &Context) -> Result<SyscallReturn> {
+fn handle_getfd(fd: FileDesc, _arg: u64, ctx: &Context) -> Result<SyscallReturn> {
     let mut file_table = ctx.thread_local.borrow_file_table_mut();
     file_table.read_with(|inner| {
         let fd_flags = inner.get_entry(fd)?.flags();
@@ -64,7 +126,7 @@ fn handle_setfd(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn>
     })
 }
 
-fn handle_getfl(fd: FileDesc, ctx: &Context) -> Result<SyscallReturn> {
+fn handle_getfl(fd: FileDesc, _arg: u64, ctx: &Context) -> Result<SyscallReturn> {
     let mut file_table = ctx.thread_local.borrow_file_table_mut();
     let file = get_file_fast!(&mut file_table, fd);
     let status_flags = file.status_flags();
@@ -82,10 +144,10 @@ fn handle_setfl(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn>
         | StatusFlags::O_DIRECT
         | StatusFlags::O_NOATIME
         | StatusFlags::O_NONBLOCK;
-    let mut status_flags = file.status_flags();
-    status_flags.remove(valid_flags_mask);
-    status_flags.insert(StatusFlags::from_bits_truncate(arg as _) & valid_flags_mask);
-    file.set_status_flags(status_flags)?;
+    // The other status flags must be kept even if they are changed concurrently (e.g., by
+    // `ioctl(FIONBIO)`), so the update must be atomic.
+    let new_flags = StatusFlags::from_bits_truncate(arg as _);
+    file.update_status_flags(valid_flags_mask, new_flags)?;
     Ok(SyscallReturn::Return(0))
 }
 
@@ -109,7 +171,18 @@ fn handle_getlk(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn>
     Ok(SyscallReturn::Return(0))
 }
 
-fn handle_setlk(
+fn handle_setlk(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn> {
+    set_range_lock(fd, arg, true, ctx)
+}
+
+fn handle_setlkw(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn> {
+    set_range_lock(fd, arg, false, ctx).map_err(|err| match err.error() {
+        Errno::EINTR => Error::new(Errno::ERESTARTSYS),
+        _ => err,
+    })
+}
+
+fn set_range_lock(
     fd: FileDesc,
     arg: u64,
     is_nonblocking: bool,
@@ -129,39 +202,134 @@ fn handle_setlk(
     Ok(SyscallReturn::Return(0))
 }
 
-fn handle_getown(fd: FileDesc, ctx: &Context) -> Result<SyscallReturn> {
+fn handle_getown(fd: FileDesc, _arg: u64, ctx: &Context) -> Result<SyscallReturn> {
     let mut file_table = ctx.thread_local.borrow_file_table_mut();
     file_table.read_with(|inner| {
-        let pid = inner.get_entry(fd)?.owner().unwrap_or(0);
-        Ok(SyscallReturn::Return(pid as _))
+        let owner = match inner.get_entry(fd)?.owner() {
+            Some(OwnerId::Thread(tid)) => tid as isize,
+            Some(OwnerId::Process(pid)) => pid as isize,
+            Some(OwnerId::ProcessGroup(pgid)) => -(pgid as isize),
+            None => 0,
+        };
+        Ok(SyscallReturn::Return(owner))
     })
 }
 
 fn handle_setown(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn> {
     // A process ID is specified as a positive value; a process group ID is specified as a negative value.
-    let abs_arg = (arg as i32).unsigned_abs();
-    if abs_arg > i32::MAX as u32 {
+    let arg = arg as i32;
+    if arg == i32::MIN {
         return_errno_with_message!(Errno::EINVAL, "process (group) id overflowed");
     }
-    let pid = Pid::try_from(abs_arg)
-        .map_err(|_| Error::with_message(Errno::EINVAL, "invalid process (group) id"))?;
 
-    let owner_process = if pid == 0 {
-        None
+    let owner_type = if arg < 0 {
+        OwnerType::F_OWNER_PGRP
     } else {
-        Some(process_table::get_process(pid).ok_or(Error::with_message(
-            Errno::ESRCH,
-            "cannot set_owner with an invalid pid",
-        ))?)
+        OwnerType::F_OWNER_PID
+    };
+    set_owner(fd, owner_type, arg.unsigned_abs(), ctx)
+}
+
+fn handle_getown_ex(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn> {
+    let owner = {
+        let mut file_table = ctx.thread_local.borrow_file_table_mut();
+        file_table.read_with(|inner| inner.get_entry(fd).map(|entry| entry.owner()))?
+    };
+
+    // Like Linux, a file descriptor without an owner is reported to be owned by thread 0.
+    let (type_, pid) = match owner {
+        Some(OwnerId::Thread(tid)) => (OwnerType::F_OWNER_TID, tid),
+        Some(OwnerId::Process(pid)) => (OwnerType::F_OWNER_PID, pid),
+        Some(OwnerId::ProcessGroup(pgid)) => (OwnerType::F_OWNER_PGRP, pgid),
+        None => (OwnerType::F_OWNER_TID, 0),
+    };
+    let owner_ex = c_f_owner_ex {
+        type_: type_ as i32,
+        pid: pid as i32,
+    };
+    ctx.user_space().write_val(arg as Vaddr, &owner_ex)?;
+
+    Ok(SyscallReturn::Return(0))
+}
+
+fn handle_setown_ex(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn> {
+    let owner_ex = ctx.user_space().read_val::<c_f_owner_ex>(arg as Vaddr)?;
+    let owner_type = OwnerType::try_from(owner_ex.type_)?;
+    if owner_ex.pid < 0 {
+        return_errno_with_message!(Errno::EINVAL, "the process (group) id is negative");
+    }
+
+    set_owner(fd, owner_type, owner_ex.pid as u32, ctx)
+}
+
+fn set_owner(fd: FileDesc, owner_type: OwnerType, id: u32, ctx: &Context) -> Result<SyscallReturn> {
+    let owner = match owner_type {
+        _ if id == 0 => None,
+        OwnerType::F_OWNER_TID => {
+            let thread = thread_table::get_thread(id)
+                .ok_or_else(|| Error::with_message(Errno::ESRCH, "the thread does not exist"))?;
+            Some(FileOwner::Thread(thread))
+        }
+        OwnerType::F_OWNER_PID => {
+            let process = process_table::get_process(id)
+                .ok_or_else(|| Error::with_message(Errno::ESRCH, "the process does not exist"))?;
+            Some(FileOwner::Process(process))
+        }
+        OwnerType::F_OWNER_PGRP => {
+            let process_group = process_table::get_process_group(&id).ok_or_else(|| {
+                Error::with_message(Errno::ESRCH, "the process group does not exist")
+            })?;
+            Some(FileOwner::ProcessGroup(process_group))
+        }
     };
 
     let file_table = ctx.thread_local.borrow_file_table();
     let mut file_table_locked = file_table.unwrap().write();
     let file_entry = file_table_locked.get_entry_mut(fd)?;
-    file_entry.set_owner(owner_process.as_ref())?;
+    file_entry.set_owner(owner)?;
+    Ok(SyscallReturn::Return(0))
+}
+
+fn handle_getpipe_sz(fd: FileDesc, _arg: u64, ctx: &Context) -> Result<SyscallReturn> {
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, fd);
+    let capacity = file.pipe_capacity()?;
+    Ok(SyscallReturn::Return(capacity as _))
+}
+
+fn handle_setpipe_sz(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn> {
+    let is_privileged = ctx
+        .posix_thread
+        .credentials()
+        .has_capability(CapSet::SYS_RESOURCE);
+
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, fd);
+    // The argument is an `unsigned int` on Linux.
+    let capacity = pipe::set_pipe_capacity(&**file, arg as u32 as usize, is_privileged)?;
+    Ok(SyscallReturn::Return(capacity as _))
+}
+
+fn handle_add_seals(fd: FileDesc, arg: u64, ctx: &Context) -> Result<SyscallReturn> {
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, fd);
+    if !file.access_mode().is_writable() {
+        return_errno_with_message!(Errno::EPERM, "the file is not opened for writing");
+    }
+    // The argument is an `unsigned int` on Linux.
+    let seals = FileSeals::from_bits(arg as u32)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid seals"))?;
+    file.add_seals(seals)?;
     Ok(SyscallReturn::Return(0))
 }
 
+fn handle_get_seals(fd: FileDesc, _arg: u64, ctx: &Context) -> Result<SyscallReturn> {
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, fd);
+    let seals = file.seals()?;
+    Ok(SyscallReturn::Return(seals.bits() as _))
+}
+
 #[repr(i32)]
 #[derive(Debug, Clone, Copy, TryFromInt)]
 #[expect(non_camel_case_types)]
@@ -176,7 +344,32 @@ enum FcntlCmd {
     F_SETLKW = 7,
     F_SETOWN = 8,
     F_GETOWN = 9,
+    F_SETOWN_EX = 15,
+    F_GETOWN_EX = 16,
     F_DUPFD_CLOEXEC = 1030,
+    F_SETPIPE_SZ = 1031,
+    F_GETPIPE_SZ = 1032,
+    F_ADD_SEALS = 1033,
+    F_GET_SEALS = 1034,
+}
+
+/// The type of the owner in [`c_f_owner_ex`].
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[expect(non_camel_case_types)]
+enum OwnerType {
+    F_OWNER_TID = 0,
+    F_OWNER_PID = 1,
+    F_OWNER_PGRP = 2,
+}
+
+/// C struct for the owner of a file descriptor in Libc
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod)]
+#[expect(non_camel_case_types)]
+struct c_f_owner_ex {
+    type_: i32,
+    pid: i32,
 }
 
 #[expect(non_camel_case_types)]
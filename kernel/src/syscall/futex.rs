@@ -6,8 +6,9 @@ use crate::{
     current_userspace,
     prelude::*,
     process::posix_thread::futex::{
-        futex_op_and_flags_from_u32, futex_requeue, futex_wait, futex_wait_bitset, futex_wake,
-        futex_wake_bitset, FutexFlags, FutexOp,
+        futex_lock_pi, futex_op_and_flags_from_u32, futex_requeue, futex_unlock_pi, futex_wait,
+        futex_wait_bitset, futex_wake, futex_wake_bitset, futex_wake_op, FutexFlags, FutexOp,
+        FutexWakeOp,
     },
     syscall::SyscallReturn,
     time::{
@@ -55,6 +56,9 @@ pub fn sys_futex(
             // Ref: <https://github.com/torvalds/linux/commit/4fbf5d6837bf81fd7a27d771358f4ee6c4f243f8>
             return_errno_with_message!(Errno::ENOSYS, "FUTEX_WAIT cannot use CLOCK_REALTIME");
         }
+        // From man(2) futex:
+        // for FUTEX_LOCK_PI, the timeout is measured against the CLOCK_REALTIME clock.
+        let is_real_time = is_real_time || futex_op == FutexOp::FUTEX_LOCK_PI;
 
         let timeout = {
             // From man(2) futex:
@@ -127,10 +131,50 @@ pub fn sys_futex(
                 max_nwakes,
                 max_nrequeues,
                 futex_new_addr as _,
+                None,
+                ctx,
+                pid,
+            )
+            .map(|(nwakes, _)| nwakes as _)
+        }
+        FutexOp::FUTEX_CMP_REQUEUE => {
+            let max_nwakes = get_futex_val(futex_val as i32)?;
+            let max_nrequeues = get_futex_val(utime_addr as i32)?;
+            futex_requeue(
+                futex_addr as _,
+                max_nwakes,
+                max_nrequeues,
+                futex_new_addr as _,
+                Some(bitset as _),
+                ctx,
+                pid,
+            )
+            .map(|(nwakes, nrequeues)| (nwakes + nrequeues) as _)
+        }
+        FutexOp::FUTEX_WAKE_OP => {
+            // Negative counts are treated as zero, as Linux does.
+            let max_nwakes = (futex_val as i32).max(0) as usize;
+            let max_nwakes2 = (utime_addr as i32).max(0) as usize;
+            let wake_op = FutexWakeOp::from_u32(bitset as _)?;
+            futex_wake_op(
+                futex_addr as _,
+                max_nwakes,
+                futex_new_addr as _,
+                max_nwakes2,
+                wake_op,
+                ctx,
                 pid,
             )
             .map(|nwakes| nwakes as _)
         }
+        FutexOp::FUTEX_LOCK_PI => {
+            let timeout = get_futex_timeout(utime_addr)?;
+            futex_lock_pi(futex_addr as _, timeout, false, ctx, pid).map(|_| 0)
+        }
+        FutexOp::FUTEX_TRYLOCK_PI => {
+            futex_lock_pi(futex_addr as _, None, true, ctx, pid).map(|_| 0)
+        }
+        FutexOp::FUTEX_UNLOCK_PI => futex_unlock_pi(futex_addr as _, ctx, pid).map(|_| 0),
         _ => {
             warn!("futex op = {:?}", futex_op);
             return_errno_with_message!(Errno::EINVAL, "unsupported futex op");
@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    prelude::*,
+    process::posix_thread::{thread_table, RobustListHead},
+    thread::Tid,
+};
+
+pub fn sys_get_robust_list(
+    tid: Tid,
+    robust_list_head_ptr_ptr: Vaddr,
+    len_ptr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "tid = {}, robust list head ptr ptr: 0x{:x}, len ptr: 0x{:x}",
+        tid, robust_list_head_ptr_ptr, len_ptr
+    );
+
+    if tid != 0 && tid != ctx.posix_thread.tid() {
+        if tid > (i32::MAX as u32) || thread_table::get_thread(tid).is_none() {
+            return_errno_with_message!(Errno::ESRCH, "the thread does not exist");
+        }
+        // TODO: Support getting the robust lists of other threads. The robust list is stored in
+        // the thread-local data, which cannot be accessed by other threads.
+        return_errno_with_message!(
+            Errno::EPERM,
+            "getting the robust list of other threads is not supported"
+        );
+    }
+
+    let robust_list_head_ptr = ctx.thread_local.robust_list().borrow().unwrap_or(0);
+
+    let user_space = ctx.user_space();
+    user_space.write_val(len_ptr, &core::mem::size_of::<RobustListHead>())?;
+    user_space.write_val(robust_list_head_ptr_ptr, &robust_list_head_ptr)?;
+
+    Ok(SyscallReturn::Return(0))
+}
@@ -10,6 +10,7 @@ pub fn sys_getcwd(buf: Vaddr, len: usize, ctx: &Context) -> Result<SyscallReturn
     let current = ctx.posix_thread;
     let dirent = current
         .fs()
+        .read()
         .resolver()
         .read()
         .lookup(&FsPath::new(AT_FDCWD, "").unwrap())
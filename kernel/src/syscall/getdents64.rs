@@ -28,7 +28,7 @@ pub fn sys_getdents(
     if inode_handle.dentry().type_() != InodeType::Dir {
         return_errno!(Errno::ENOTDIR);
     }
-    let mut buffer = vec![0u8; buf_len];
+    let mut buffer = vec![0u8; buf_len.min(MAX_BUF_LEN)];
     let mut reader = DirentBufferReader::<Dirent>::new(&mut buffer); // Use the non-64-bit reader
     let _ = inode_handle.readdir(&mut reader)?;
     let read_len = reader.read_len();
@@ -54,7 +54,7 @@ pub fn sys_getdents64(
     if inode_handle.dentry().type_() != InodeType::Dir {
         return_errno!(Errno::ENOTDIR);
     }
-    let mut buffer = vec![0u8; buf_len];
+    let mut buffer = vec![0u8; buf_len.min(MAX_BUF_LEN)];
     let mut reader = DirentBufferReader::<Dirent64>::new(&mut buffer);
     let _ = inode_handle.readdir(&mut reader)?;
     let read_len = reader.read_len();
@@ -63,6 +63,12 @@ pub fn sys_getdents64(
     Ok(SyscallReturn::Return(read_len as _))
 }
 
+/// The maximum length of the kernel buffer for the directory entries.
+///
+/// The length of the user buffer is not trusted. The entries that do not fit in the kernel buffer
+/// are left to the following calls.
+const MAX_BUF_LEN: usize = 64 * 1024;
+
 /// The DirentSerializer can decide how to serialize the data.
 trait DirentSerializer {
     /// Create a DirentSerializer.
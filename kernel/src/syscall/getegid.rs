@@ -1,10 +1,13 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::Gid};
+use crate::prelude::*;
 
 pub fn sys_getegid(ctx: &Context) -> Result<SyscallReturn> {
-    let egid = ctx.posix_thread.credentials().egid();
+    let credentials = ctx.posix_thread.credentials();
+    let egid = credentials
+        .user_ns()
+        .gid_from_kernel_or_overflow(credentials.egid());
 
-    Ok(SyscallReturn::Return(<Gid as Into<u32>>::into(egid) as _))
+    Ok(SyscallReturn::Return(egid as _))
 }
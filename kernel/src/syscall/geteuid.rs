@@ -1,10 +1,13 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::Uid};
+use crate::prelude::*;
 
 pub fn sys_geteuid(ctx: &Context) -> Result<SyscallReturn> {
-    let euid = ctx.posix_thread.credentials().euid();
+    let credentials = ctx.posix_thread.credentials();
+    let euid = credentials
+        .user_ns()
+        .uid_from_kernel_or_overflow(credentials.euid());
 
-    Ok(SyscallReturn::Return(<Uid as Into<u32>>::into(euid) as _))
+    Ok(SyscallReturn::Return(euid as _))
 }
@@ -1,10 +1,13 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::Gid};
+use crate::prelude::*;
 
 pub fn sys_getgid(ctx: &Context) -> Result<SyscallReturn> {
-    let gid = ctx.posix_thread.credentials().rgid();
+    let credentials = ctx.posix_thread.credentials();
+    let gid = credentials
+        .user_ns()
+        .gid_from_kernel_or_overflow(credentials.rgid());
 
-    Ok(SyscallReturn::Return(<Gid as Into<u32>>::into(gid) as _))
+    Ok(SyscallReturn::Return(gid as _))
 }
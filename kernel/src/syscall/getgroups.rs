@@ -11,6 +11,7 @@ pub fn sys_getgroups(size: i32, group_list_addr: Vaddr, ctx: &Context) -> Result
     }
 
     let credentials = ctx.posix_thread.credentials();
+    let user_ns = credentials.user_ns();
     let groups = credentials.groups();
 
     if size == 0 {
@@ -26,8 +27,8 @@ pub fn sys_getgroups(size: i32, group_list_addr: Vaddr, ctx: &Context) -> Result
 
     let user_space = ctx.user_space();
     for (idx, gid) in groups.iter().enumerate() {
-        let addr = group_list_addr + idx * core::mem::size_of_val(gid);
-        user_space.write_val(addr, gid)?;
+        let addr = group_list_addr + idx * core::mem::size_of::<u32>();
+        user_space.write_val(addr, &user_ns.gid_from_kernel_or_overflow(*gid))?;
     }
 
     Ok(SyscallReturn::Return(groups.len() as _))
@@ -10,15 +10,14 @@ pub fn sys_getrandom(buf: Vaddr, count: usize, flags: u32, ctx: &Context) -> Res
         buf, count, flags
     );
     // TODO: support nonblock flag.
-    // Currently our getrandom implementation relies on x86-specific `rdrand` instruction, so it will never block.
-    let mut buffer = vec![0u8; count];
+    // The RNG has been seeded at boot time (see `util::random::init`), so it will never block.
+    let user_space = ctx.user_space();
+    let mut writer = user_space.writer(buf, count)?;
     let read_len = if flags.contains(GetRandomFlags::GRND_RANDOM) {
-        device::Random::getrandom(&mut buffer)?
+        device::Random::getrandom(&mut writer)?
     } else {
-        device::Urandom::getrandom(&mut buffer)?
+        device::Urandom::getrandom(&mut writer)?
     };
-    ctx.user_space()
-        .write_bytes(buf, &mut VmReader::from(buffer.as_slice()))?;
     Ok(SyscallReturn::Return(read_len as isize))
 }
 
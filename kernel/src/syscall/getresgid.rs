@@ -12,15 +12,16 @@ pub fn sys_getresgid(
     debug!("rgid_ptr = 0x{rgid_ptr:x}, egid_ptr = 0x{egid_ptr:x}, sgid_ptr = 0x{sgid_ptr:x}");
 
     let credentials = ctx.posix_thread.credentials();
+    let user_ns = credentials.user_ns();
     let user_space = ctx.user_space();
 
-    let rgid = credentials.rgid();
+    let rgid = user_ns.gid_from_kernel_or_overflow(credentials.rgid());
     user_space.write_val(rgid_ptr, &rgid)?;
 
-    let egid = credentials.egid();
+    let egid = user_ns.gid_from_kernel_or_overflow(credentials.egid());
     user_space.write_val(egid_ptr, &egid)?;
 
-    let sgid = credentials.sgid();
+    let sgid = user_ns.gid_from_kernel_or_overflow(credentials.sgid());
     user_space.write_val(sgid_ptr, &sgid)?;
 
     Ok(SyscallReturn::Return(0))
@@ -12,15 +12,16 @@ pub fn sys_getresuid(
     debug!("ruid_ptr = 0x{ruid_ptr:x}, euid_ptr = 0x{euid_ptr:x}, suid_ptr = 0x{suid_ptr:x}");
 
     let credentials = ctx.posix_thread.credentials();
+    let user_ns = credentials.user_ns();
     let user_space = ctx.user_space();
 
-    let ruid = credentials.ruid();
+    let ruid = user_ns.uid_from_kernel_or_overflow(credentials.ruid());
     user_space.write_val(ruid_ptr, &ruid)?;
 
-    let euid = credentials.euid();
+    let euid = user_ns.uid_from_kernel_or_overflow(credentials.euid());
     user_space.write_val(euid_ptr, &euid)?;
 
-    let suid = credentials.suid();
+    let suid = user_ns.uid_from_kernel_or_overflow(credentials.suid());
     user_space.write_val(suid_ptr, &suid)?;
 
     Ok(SyscallReturn::Return(0))
@@ -3,7 +3,7 @@
 use int_to_c_enum::TryFromInt;
 
 use super::SyscallReturn;
-use crate::{prelude::*, time::timeval_t};
+use crate::{prelude::*, process::Process, time::timeval_t};
 
 #[derive(Debug, Copy, Clone, TryFromInt, PartialEq)]
 #[repr(i32)]
@@ -24,14 +24,7 @@ pub fn sys_getrusage(target: i32, rusage_addr: Vaddr, ctx: &Context) -> Result<S
 
     if rusage_addr != 0 {
         let rusage = match rusage_target {
-            RusageTarget::ForSelf => {
-                let process = ctx.process;
-                rusage_t {
-                    ru_utime: process.prof_clock().user_clock().read_time().into(),
-                    ru_stime: process.prof_clock().kernel_clock().read_time().into(),
-                    ..Default::default()
-                }
-            }
+            RusageTarget::ForSelf => rusage_t::of_process(ctx.process),
             RusageTarget::Thread => {
                 let posix_thread = ctx.posix_thread;
                 rusage_t {
@@ -90,3 +83,18 @@ pub struct rusage_t {
     /// involuntary
     pub ru_nivcsw: u64,
 }
+
+impl rusage_t {
+    /// Returns the resource usage of the process.
+    ///
+    /// Only the CPU times and the maximum resident set size are reported.
+    pub(super) fn of_process(process: &Process) -> Self {
+        Self {
+            ru_utime: process.prof_clock().user_clock().read_time().into(),
+            ru_stime: process.prof_clock().kernel_clock().read_time().into(),
+            // The maximum resident set size is in kilobytes.
+            ru_maxrss: (process.vm().max_resident_pages() * (PAGE_SIZE / 1024)) as u64,
+            ..Default::default()
+        }
+    }
+}
@@ -1,10 +1,13 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::Uid};
+use crate::prelude::*;
 
 pub fn sys_getuid(ctx: &Context) -> Result<SyscallReturn> {
-    let uid = ctx.posix_thread.credentials().ruid();
+    let credentials = ctx.posix_thread.credentials();
+    let uid = credentials
+        .user_ns()
+        .uid_from_kernel_or_overflow(credentials.ruid());
 
-    Ok(SyscallReturn::Return(<Uid as Into<u32>>::into(uid) as _))
+    Ok(SyscallReturn::Return(uid as _))
 }
@@ -0,0 +1,91 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    fs::{
+        file_table::{get_file_fast, FdCreationFlags, FileDesc},
+        fs_resolver::{FsPath, AT_FDCWD},
+        inotify::{InotifyFile, InotifyMask},
+        utils::{CreationFlags, StatusFlags},
+    },
+    prelude::*,
+    syscall::constants::MAX_FILENAME_LEN,
+};
+
+pub fn sys_inotify_init(ctx: &Context) -> Result<SyscallReturn> {
+    sys_inotify_init1(0, ctx)
+}
+
+pub fn sys_inotify_init1(flags: u32, ctx: &Context) -> Result<SyscallReturn> {
+    let flags = Flags::from_bits(flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "unknown flags"))?;
+    debug!("flags = {:?}", flags);
+
+    let inotify_file = InotifyFile::new(flags.contains(Flags::IN_NONBLOCK));
+    let fd = {
+        let file_table = ctx.thread_local.borrow_file_table();
+        let mut file_table_locked = file_table.unwrap().write();
+        let fd_flags = FdCreationFlags::from_bits_truncate(flags.bits()).fd_flags();
+        file_table_locked.insert(inotify_file, fd_flags)?
+    };
+
+    Ok(SyscallReturn::Return(fd as _))
+}
+
+pub fn sys_inotify_add_watch(
+    fd: FileDesc,
+    path_ptr: Vaddr,
+    mask: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let path = ctx.user_space().read_cstring(path_ptr, MAX_FILENAME_LEN)?;
+    let mask = InotifyMask::from_bits(mask)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "unknown mask bits"))?;
+    debug!("fd = {}, path = {:?}, mask = {:?}", fd, path, mask);
+
+    let dentry = {
+        let path = path.to_string_lossy();
+        let fs_path = FsPath::new(AT_FDCWD, path.as_ref())?;
+        let fs_ref = ctx.posix_thread.fs().read();
+        let fs = fs_ref.resolver().read();
+        if mask.contains(InotifyMask::IN_DONT_FOLLOW) {
+            fs.lookup_no_follow(&fs_path)?
+        } else {
+            fs.lookup(&fs_path)?
+        }
+    };
+
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, fd);
+    let inotify_file = file
+        .downcast_ref::<InotifyFile>()
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "not an inotify file"))?;
+
+    let wd = inotify_file.add_watch(dentry.inode().clone(), mask)?;
+
+    Ok(SyscallReturn::Return(wd as _))
+}
+
+pub fn sys_inotify_rm_watch(fd: FileDesc, wd: i32, ctx: &Context) -> Result<SyscallReturn> {
+    debug!("fd = {}, wd = {}", fd, wd);
+
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, fd);
+    let inotify_file = file
+        .downcast_ref::<InotifyFile>()
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "not an inotify file"))?;
+
+    if wd < 0 {
+        return_errno_with_message!(Errno::EINVAL, "the watch descriptor is negative");
+    }
+    inotify_file.rm_watch(wd as u32)?;
+
+    Ok(SyscallReturn::Return(0))
+}
+
+bitflags! {
+    struct Flags: u32 {
+        const IN_CLOEXEC = CreationFlags::O_CLOEXEC.bits();
+        const IN_NONBLOCK = StatusFlags::O_NONBLOCK.bits();
+    }
+}
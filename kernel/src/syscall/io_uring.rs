@@ -0,0 +1,238 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    fs::{
+        file_handle::FileLike,
+        file_table::{get_file_fast, FdFlags, FileDesc},
+        io_uring::{
+            is_opcode_supported, IoUringBufReg, IoUringEnterFlags, IoUringFeatures, IoUringFile,
+            IoUringParams, IoUringSetupFlags, IORING_MAX_CQ_ENTRIES, IORING_MAX_ENTRIES,
+            IORING_OP_LAST_SUPPORTED,
+        },
+    },
+    prelude::*,
+    process::signal::{sig_mask::SigMask, with_sigmask_changed},
+};
+
+pub fn sys_io_uring_setup(
+    entries: u32,
+    params_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!("entries = {}, params_addr = 0x{:x}", entries, params_addr);
+
+    let user_space = ctx.user_space();
+    let mut params = user_space.read_val::<IoUringParams>(params_addr)?;
+    if params.resv.iter().any(|resv| *resv != 0) {
+        return_errno_with_message!(Errno::EINVAL, "the reserved fields must be zero");
+    }
+    let flags = IoUringSetupFlags::from_bits(params.flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the flags are not supported"))?;
+
+    let (sq_entries, cq_entries) = compute_entries(entries, params.cq_entries, flags)?;
+    let io_uring = IoUringFile::new(sq_entries, cq_entries, flags)?;
+
+    params.sq_entries = sq_entries;
+    params.cq_entries = cq_entries;
+    params.features = (IoUringFeatures::SINGLE_MMAP
+        | IoUringFeatures::NODROP
+        | IoUringFeatures::SUBMIT_STABLE
+        | IoUringFeatures::RW_CUR_POS)
+        .bits();
+    params.sq_off = io_uring.sq_offsets();
+    params.cq_off = io_uring.cq_offsets();
+    user_space.write_val(params_addr, &params)?;
+
+    let file_table = ctx.thread_local.borrow_file_table();
+    let fd = file_table
+        .unwrap()
+        .write()
+        .insert(io_uring, FdFlags::CLOEXEC)?;
+    Ok(SyscallReturn::Return(fd as _))
+}
+
+/// Computes the numbers of SQ entries and CQ entries, which are both powers of two.
+fn compute_entries(entries: u32, cq_entries: u32, flags: IoUringSetupFlags) -> Result<(u32, u32)> {
+    let clamp = |entries: u32, max_entries: u32| -> Result<u32> {
+        if entries == 0 {
+            return_errno_with_message!(Errno::EINVAL, "the number of entries cannot be zero");
+        }
+        if entries <= max_entries {
+            Ok(entries.next_power_of_two())
+        } else if flags.contains(IoUringSetupFlags::CLAMP) {
+            Ok(max_entries)
+        } else {
+            return_errno_with_message!(Errno::EINVAL, "the number of entries is too large");
+        }
+    };
+
+    let sq_entries = clamp(entries, IORING_MAX_ENTRIES)?;
+
+    let cq_entries = if flags.contains(IoUringSetupFlags::CQSIZE) {
+        let cq_entries = clamp(cq_entries, IORING_MAX_CQ_ENTRIES)?;
+        if cq_entries < sq_entries {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the number of CQ entries is less than the number of SQ entries"
+            );
+        }
+        cq_entries
+    } else {
+        sq_entries * 2
+    };
+
+    Ok((sq_entries, cq_entries))
+}
+
+pub fn sys_io_uring_enter(
+    fd: FileDesc,
+    to_submit: u32,
+    min_complete: u32,
+    flags: u32,
+    sigmask_addr: Vaddr,
+    sigmask_size: usize,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "fd = {}, to_submit = {}, min_complete = {}, flags = 0x{:x}, sigmask_addr = 0x{:x}, sigmask_size = {}",
+        fd, to_submit, min_complete, flags, sigmask_addr, sigmask_size
+    );
+
+    let flags = IoUringEnterFlags::from_bits(flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the flags are not supported"))?;
+
+    let file = {
+        let mut file_table = ctx.thread_local.borrow_file_table_mut();
+        get_file_fast!(&mut file_table, fd).into_owned()
+    };
+    let io_uring = downcast_io_uring(&file)?;
+
+    let num_submitted = if to_submit > 0 {
+        io_uring.submit(to_submit, ctx)
+    } else {
+        0
+    };
+    io_uring.flush_completions();
+
+    if !flags.contains(IoUringEnterFlags::GETEVENTS) {
+        return Ok(SyscallReturn::Return(num_submitted as _));
+    }
+
+    let wait = || io_uring.wait_completions(min_complete);
+    let res = if sigmask_addr != 0 {
+        if sigmask_size != size_of::<SigMask>() {
+            return_errno_with_message!(Errno::EINVAL, "sigmask size is invalid");
+        }
+        let sigmask = ctx.user_space().read_val::<SigMask>(sigmask_addr)?;
+        with_sigmask_changed(ctx, |_: SigMask| sigmask, wait)
+    } else {
+        wait()
+    };
+
+    // Like Linux, the error of waiting is reported only if no request has been submitted.
+    match res {
+        Err(err) if num_submitted == 0 => Err(err),
+        _ => Ok(SyscallReturn::Return(num_submitted as _)),
+    }
+}
+
+pub fn sys_io_uring_register(
+    fd: FileDesc,
+    opcode: u32,
+    arg: Vaddr,
+    nr_args: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "fd = {}, opcode = {}, arg = 0x{:x}, nr_args = {}",
+        fd, opcode, arg, nr_args
+    );
+
+    const IORING_REGISTER_PROBE: u32 = 8;
+    const IORING_REGISTER_PBUF_RING: u32 = 22;
+    const IORING_UNREGISTER_PBUF_RING: u32 = 23;
+
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, fd);
+    let io_uring = downcast_io_uring(&file)?;
+
+    match opcode {
+        IORING_REGISTER_PROBE => register_probe(arg, nr_args, ctx)?,
+        IORING_REGISTER_PBUF_RING | IORING_UNREGISTER_PBUF_RING => {
+            if nr_args != 1 {
+                return_errno_with_message!(Errno::EINVAL, "the number of arguments must be one");
+            }
+            let reg = ctx.user_space().read_val::<IoUringBufReg>(arg)?;
+            if opcode == IORING_REGISTER_PBUF_RING {
+                io_uring.register_buf_ring(&reg)?;
+            } else {
+                io_uring.unregister_buf_ring(&reg)?;
+            }
+        }
+        _ => return_errno_with_message!(Errno::EINVAL, "the register opcode is not supported"),
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
+
+fn downcast_io_uring(file: &dyn FileLike) -> Result<&IoUringFile> {
+    file.downcast_ref::<IoUringFile>()
+        .ok_or_else(|| Error::with_message(Errno::EOPNOTSUPP, "the file is not an io_uring file"))
+}
+
+/// Reports the supported opcodes to the user space.
+fn register_probe(arg: Vaddr, nr_args: u32, ctx: &Context) -> Result<()> {
+    let nr_ops = nr_args.min(IORING_OP_LAST_SUPPORTED as u32 + 1) as usize;
+    let size = size_of::<CIoUringProbe>() + nr_ops * size_of::<CIoUringProbeOp>();
+
+    let user_space = ctx.user_space();
+    let mut buf = vec![0u8; size];
+    user_space.read_bytes(arg, &mut VmWriter::from(buf.as_mut_slice()))?;
+    if buf.iter().any(|byte| *byte != 0) {
+        return_errno_with_message!(Errno::EINVAL, "the probe must be zeroed");
+    }
+
+    let probe = CIoUringProbe {
+        last_op: IORING_OP_LAST_SUPPORTED,
+        ops_len: nr_ops as u8,
+        ..CIoUringProbe::new_zeroed()
+    };
+    user_space.write_val(arg, &probe)?;
+
+    for op in 0..nr_ops {
+        let probe_op = CIoUringProbeOp {
+            op: op as u8,
+            flags: if is_opcode_supported(op as u8) {
+                IO_URING_OP_SUPPORTED
+            } else {
+                0
+            },
+            ..CIoUringProbeOp::new_zeroed()
+        };
+        let addr = arg + size_of::<CIoUringProbe>() + op * size_of::<CIoUringProbeOp>();
+        user_space.write_val(addr, &probe_op)?;
+    }
+
+    Ok(())
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CIoUringProbe {
+    last_op: u8,
+    ops_len: u8,
+    resv: u16,
+    resv2: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CIoUringProbeOp {
+    op: u8,
+    resv: u8,
+    flags: u16,
+    resv2: u32,
+}
+
+const IO_URING_OP_SUPPORTED: u16 = 1;
@@ -21,20 +21,20 @@ pub fn sys_ioctl(fd: FileDesc, cmd: u32, arg: Vaddr, ctx: &Context) -> Result<Sy
     let res = match ioctl_cmd {
         IoctlCmd::FIONBIO => {
             let is_nonblocking = ctx.user_space().read_val::<i32>(arg)? != 0;
-            let mut flags = file.status_flags();
+            let mut flags = StatusFlags::empty();
             flags.set(StatusFlags::O_NONBLOCK, is_nonblocking);
-            file.set_status_flags(flags)?;
+            file.update_status_flags(StatusFlags::O_NONBLOCK, flags)?;
             0
         }
         IoctlCmd::FIOASYNC => {
             let is_async = ctx.user_space().read_val::<i32>(arg)? != 0;
-            let mut flags = file.status_flags();
+            let mut flags = StatusFlags::empty();
 
             // Set `O_ASYNC` flags will send `SIGIO` signal to a process when
             // I/O is possible, user should call `fcntl(fd, F_SETOWN, pid)`
             // first to let the kernel know just whom to notify.
             flags.set(StatusFlags::O_ASYNC, is_async);
-            file.set_status_flags(flags)?;
+            file.update_status_flags(StatusFlags::O_ASYNC, flags)?;
             0
         }
         IoctlCmd::FIOCLEX => {
@@ -0,0 +1,150 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_block::ioprio::{IoPrioClass, IoPriority};
+
+use super::SyscallReturn;
+use crate::{
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        posix_thread::{thread_table, AsPosixThread},
+        Pgid, Uid,
+    },
+    syscall::get_priority::{get_processes, PriorityTarget},
+    thread::{AsThread, Thread, Tid},
+};
+
+pub fn sys_ioprio_set(which: i32, who: u32, ioprio: i32, ctx: &Context) -> Result<SyscallReturn> {
+    let io_priority = u16::try_from(ioprio)
+        .ok()
+        .and_then(IoPriority::from_raw)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid I/O priority"))?;
+    let target = IoPrioTarget::new(which, who, ctx)?;
+    debug!(
+        "ioprio_set target: {:?}, io_priority: {:?}",
+        target, io_priority
+    );
+
+    let credentials = ctx.posix_thread.credentials();
+    let has_nice_capability = credentials.has_capability(CapSet::SYS_NICE);
+    if io_priority.class() == IoPrioClass::RealTime && !has_nice_capability {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "the real-time I/O class requires the CAP_SYS_NICE capability"
+        );
+    }
+
+    for thread in target.threads(ctx)? {
+        let posix_thread = thread.as_posix_thread().unwrap();
+
+        // Only the threads of the same user can be changed without the `CAP_SYS_NICE`
+        // capability.
+        let (target_ruid, target_euid) = {
+            let target_credentials = posix_thread.credentials();
+            (target_credentials.ruid(), target_credentials.euid())
+        };
+        if !has_nice_capability
+            && target_ruid != credentials.euid()
+            && target_euid != credentials.euid()
+        {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "changing the I/O priorities of other users requires the CAP_SYS_NICE capability"
+            );
+        }
+
+        posix_thread.set_io_priority(io_priority);
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
+
+pub fn sys_ioprio_get(which: i32, who: u32, ctx: &Context) -> Result<SyscallReturn> {
+    let target = IoPrioTarget::new(which, who, ctx)?;
+    debug!("ioprio_get target: {:?}", target);
+
+    // Returns the highest I/O priority enjoyed by the threads
+    let highest_prio = target
+        .threads(ctx)?
+        .iter()
+        .map(|thread| thread.as_posix_thread().unwrap().io_priority())
+        .reduce(|highest, io_priority| {
+            if io_priority.is_higher_than(highest) {
+                io_priority
+            } else {
+                highest
+            }
+        })
+        .unwrap();
+
+    Ok(SyscallReturn::Return(highest_prio.to_raw() as _))
+}
+
+#[derive(Debug)]
+enum IoPrioTarget {
+    Thread(Tid),
+    ProcessGroup(Pgid),
+    User(Uid),
+}
+
+impl IoPrioTarget {
+    fn new(which: i32, who: u32, ctx: &Context) -> Result<Self> {
+        let which = Which::try_from(which)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "invalid which value"))?;
+        Ok(match which {
+            Which::IOPRIO_WHO_PROCESS => Self::Thread(who),
+            Which::IOPRIO_WHO_PGRP => {
+                let pgid = if who == 0 {
+                    ctx.process.pgid()
+                } else {
+                    who as Pgid
+                };
+                Self::ProcessGroup(pgid)
+            }
+            Which::IOPRIO_WHO_USER => {
+                let uid = if who == 0 {
+                    ctx.posix_thread.credentials().ruid()
+                } else {
+                    Uid::new(who)
+                };
+                Self::User(uid)
+            }
+        })
+    }
+
+    /// Returns the target threads, which are never empty.
+    fn threads(&self, ctx: &Context) -> Result<Vec<Arc<Thread>>> {
+        let prio_target = match self {
+            Self::Thread(0) => return Ok(vec![ctx.task.as_thread().unwrap().clone()]),
+            Self::Thread(tid) => {
+                let thread = thread_table::get_thread(*tid).ok_or_else(|| {
+                    Error::with_message(Errno::ESRCH, "the thread does not exist")
+                })?;
+                return Ok(vec![thread]);
+            }
+            Self::ProcessGroup(pgid) => PriorityTarget::ProcessGroup(*pgid),
+            Self::User(uid) => PriorityTarget::User(*uid),
+        };
+
+        let mut threads = Vec::new();
+        for process in get_processes(prio_target)? {
+            let tasks = process.tasks().lock();
+            threads.extend(
+                tasks
+                    .as_slice()
+                    .iter()
+                    .map(|task| task.as_thread().unwrap().clone()),
+            );
+        }
+        Ok(threads)
+    }
+}
+
+#[expect(non_camel_case_types)]
+#[derive(Clone, Debug, TryFromInt)]
+#[repr(i32)]
+enum Which {
+    IOPRIO_WHO_PROCESS = 1,
+    IOPRIO_WHO_PGRP = 2,
+    IOPRIO_WHO_USER = 3,
+}
@@ -0,0 +1,145 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::cmp::Ordering;
+
+use int_to_c_enum::TryFromInt;
+use spin::Once;
+
+use super::SyscallReturn;
+use crate::{
+    fs::file_table::FileDesc,
+    prelude::*,
+    process::{
+        posix_thread::{thread_table, AsPosixThread, PosixThread},
+        ptrace,
+    },
+    thread::Tid,
+    util::random::getrandom,
+};
+
+pub fn sys_kcmp(
+    pid1: Tid,
+    pid2: Tid,
+    type_: u32,
+    idx1: u64,
+    idx2: u64,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let type_ = KcmpType::try_from(type_)
+        .map_err(|_| Error::with_message(Errno::EINVAL, "invalid kcmp type"))?;
+    debug!(
+        "pid1 = {}, pid2 = {}, type = {:?}, idx1 = {}, idx2 = {}",
+        pid1, pid2, type_, idx1, idx2
+    );
+
+    let thread1 = thread_table::get_thread(pid1)
+        .ok_or_else(|| Error::with_message(Errno::ESRCH, "the first thread does not exist"))?;
+    let thread2 = thread_table::get_thread(pid2)
+        .ok_or_else(|| Error::with_message(Errno::ESRCH, "the second thread does not exist"))?;
+    let posix_thread1 = thread1.as_posix_thread().unwrap();
+    let posix_thread2 = thread2.as_posix_thread().unwrap();
+
+    ptrace::check_access(ctx, posix_thread1)?;
+    ptrace::check_access(ctx, posix_thread2)?;
+
+    let (key1, key2) = match type_ {
+        KcmpType::File => (
+            file_key(posix_thread1, idx1)?,
+            file_key(posix_thread2, idx2)?,
+        ),
+        KcmpType::Vm => (vm_key(posix_thread1), vm_key(posix_thread2)),
+        KcmpType::Files => (files_key(posix_thread1), files_key(posix_thread2)),
+        KcmpType::Fs => (fs_key(posix_thread1), fs_key(posix_thread2)),
+        KcmpType::Sighand => (sighand_key(posix_thread1), sighand_key(posix_thread2)),
+        // The I/O contexts and the System V semaphore undo lists are not supported, so no
+        // thread has them. Like Linux, the absent resources are considered the same.
+        KcmpType::Io | KcmpType::Sysvsem => (0, 0),
+        KcmpType::EpollTfd => {
+            return_errno_with_message!(
+                Errno::EOPNOTSUPP,
+                "comparing the target files of epoll is not supported"
+            );
+        }
+    };
+
+    let res = match obfuscate(type_, key1).cmp(&obfuscate(type_, key2)) {
+        Ordering::Equal => 0,
+        Ordering::Less => 1,
+        Ordering::Greater => 2,
+    };
+    Ok(SyscallReturn::Return(res))
+}
+
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[repr(u32)]
+enum KcmpType {
+    File = 0,
+    Vm = 1,
+    Files = 2,
+    Fs = 3,
+    Sighand = 4,
+    Io = 5,
+    Sysvsem = 6,
+    EpollTfd = 7,
+}
+
+const NR_KCMP_TYPES: usize = 8;
+
+/// Obfuscates the address of a kernel object, so that the order of the addresses is consistent
+/// but does not reveal the addresses themselves.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/kernel/kcmp.c#L30>
+fn obfuscate(type_: KcmpType, key: usize) -> usize {
+    static COOKIES: Once<[[usize; 2]; NR_KCMP_TYPES]> = Once::new();
+
+    let cookies = COOKIES.call_once(|| {
+        let mut cookies = [[0usize; 2]; NR_KCMP_TYPES];
+        for cookie in cookies.iter_mut() {
+            let mut bytes = [0u8; size_of::<usize>() * 2];
+            getrandom(&mut bytes).unwrap();
+            let (xor, mul) = bytes.split_at(size_of::<usize>());
+            cookie[0] = usize::from_ne_bytes(xor.try_into().unwrap());
+            // The multiplier must be odd, so that the multiplication is a bijection.
+            cookie[1] = usize::from_ne_bytes(mul.try_into().unwrap()) | 1;
+        }
+        cookies
+    });
+
+    let [xor, mul] = cookies[type_ as usize];
+    (key ^ xor).wrapping_mul(mul)
+}
+
+fn file_key(posix_thread: &PosixThread, fd: u64) -> Result<usize> {
+    let fd = FileDesc::try_from(fd)
+        .map_err(|_| Error::with_message(Errno::EBADF, "the file descriptor is invalid"))?;
+    let file_table = posix_thread.file_table().lock();
+    let Some(file_table) = file_table.as_ref() else {
+        return_errno_with_message!(Errno::EBADF, "the thread has no file table");
+    };
+
+    let file_table = file_table.read();
+    let file = file_table.get_file(fd)?;
+    Ok(Arc::as_ptr(file) as *const () as usize)
+}
+
+fn vm_key(posix_thread: &PosixThread) -> usize {
+    let process = posix_thread.process();
+    let vmar = process.lock_root_vmar();
+    vmar.get()
+        .map_or(0, |vmar| Arc::as_ptr(vmar.vm_space()) as usize)
+}
+
+fn files_key(posix_thread: &PosixThread) -> usize {
+    let file_table = posix_thread.file_table().lock();
+    file_table.as_ref().map_or(0, |file_table| {
+        core::ptr::from_ref(&*file_table.read()) as usize
+    })
+}
+
+fn fs_key(posix_thread: &PosixThread) -> usize {
+    Arc::as_ptr(&*posix_thread.fs().read()) as usize
+}
+
+fn sighand_key(posix_thread: &PosixThread) -> usize {
+    Arc::as_ptr(posix_thread.process().sig_dispositions()) as usize
+}
@@ -96,9 +96,8 @@ fn listxattr(
 fn get_current_xattr_namespace(ctx: &Context) -> XattrNamespace {
     let credentials = ctx.posix_thread.credentials();
     let permitted_capset = credentials.permitted_capset();
-    let effective_capset = credentials.effective_capset();
 
-    if permitted_capset.contains(CapSet::SYS_ADMIN) && effective_capset.contains(CapSet::SYS_ADMIN)
+    if permitted_capset.contains(CapSet::SYS_ADMIN) && credentials.has_capability(CapSet::SYS_ADMIN)
     {
         XattrNamespace::Trusted
     } else {
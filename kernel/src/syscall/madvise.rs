@@ -36,15 +36,28 @@ pub fn sys_madvise(
         MadviseBehavior::MADV_NORMAL
         | MadviseBehavior::MADV_SEQUENTIAL
         | MadviseBehavior::MADV_WILLNEED => {
-            // perform a read at first
-            let mut buffer = vec![0u8; len];
-            ctx.user_space()
-                .read_bytes(start, &mut VmWriter::from(buffer.as_mut_slice()))?;
+            // Perform a read at first. The pages are read one by one, so that no buffer as large
+            // as the range is needed.
+            let user_space = ctx.user_space();
+            let mut reader = user_space.reader(start, len)?;
+            let mut buffer = vec![0u8; PAGE_SIZE];
+            while reader.has_remain() {
+                reader
+                    .read_fallible(&mut VmWriter::from(buffer.as_mut_slice()))
+                    .map_err(|(err, _)| err)?;
+            }
         }
         MadviseBehavior::MADV_DONTNEED => {
             warn!("MADV_DONTNEED isn't implemented, do nothing for now.");
         }
         MadviseBehavior::MADV_FREE => madv_free(start, end, ctx)?,
+        MadviseBehavior::MADV_MERGEABLE | MadviseBehavior::MADV_UNMERGEABLE => {
+            let is_mergeable = matches!(behavior, MadviseBehavior::MADV_MERGEABLE);
+            let user_space = ctx.user_space();
+            user_space
+                .root_vmar()
+                .set_mergeable(start..end, is_mergeable)?;
+        }
         _ => todo!(),
     }
     Ok(SyscallReturn::Return(0))
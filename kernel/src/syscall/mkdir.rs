@@ -29,13 +29,14 @@ pub fn sys_mkdirat(
         let fs_path = FsPath::new(dirfd, path.as_ref())?;
         current
             .fs()
+            .read()
             .resolver()
             .read()
             .lookup_dir_and_new_basename(&fs_path, true)?
     };
 
     let inode_mode = {
-        let mask_mode = mode & !current.fs().umask().read().get();
+        let mask_mode = mode & !current.fs().read().umask().read().get();
         InodeMode::from_bits_truncate(mask_mode)
     };
     let _ = dir_dentry.new_fs_child(name.trim_end_matches('/'), InodeType::Dir, inode_mode)?;
@@ -4,11 +4,13 @@ use super::SyscallReturn;
 use crate::{
     device::get_device,
     fs::{
+        device::DeviceType,
         file_table::FileDesc,
         fs_resolver::{FsPath, AT_FDCWD},
         utils::{InodeMode, InodeType, MknodType},
     },
     prelude::*,
+    process::credentials::capabilities::CapSet,
     syscall::constants::MAX_FILENAME_LEN,
 };
 
@@ -22,7 +24,7 @@ pub fn sys_mknodat(
     let path = ctx.user_space().read_cstring(path_addr, MAX_FILENAME_LEN)?;
     let current = ctx.posix_thread;
     let inode_mode = {
-        let mask_mode = mode & !current.fs().umask().read().get();
+        let mask_mode = mode & !current.fs().read().umask().read().get();
         InodeMode::from_bits_truncate(mask_mode)
     };
     let inode_type = InodeType::from_raw_mode(mode)?;
@@ -39,26 +41,35 @@ pub fn sys_mknodat(
         let fs_path = FsPath::new(dirfd, path.as_ref())?;
         current
             .fs()
+            .read()
             .resolver()
             .read()
             .lookup_dir_and_new_basename(&fs_path, false)?
     };
 
     match inode_type {
-        InodeType::File => {
-            let _ = dir_dentry.new_fs_child(&name, InodeType::File, inode_mode)?;
+        InodeType::File | InodeType::Socket => {
+            let _ = dir_dentry.new_fs_child(&name, inode_type, inode_mode)?;
         }
         InodeType::CharDevice | InodeType::BlockDevice => {
-            let device_inode = get_device(dev)?;
-            let _ = dir_dentry.mknod(&name, inode_mode, device_inode.into())?;
+            if !current.credentials().has_capability(CapSet::MKNOD) {
+                return_errno_with_message!(
+                    Errno::EPERM,
+                    "creating device nodes requires CAP_MKNOD"
+                );
+            }
+            let device = get_device(DeviceType::try_from(inode_type)?, dev)?;
+            let _ = dir_dentry.mknod(&name, inode_mode, device.into())?;
         }
         InodeType::NamedPipe => {
             let _ = dir_dentry.mknod(&name, inode_mode, MknodType::NamedPipeNode)?;
         }
-        InodeType::Socket => {
-            return_errno_with_message!(Errno::EINVAL, "unsupported file types")
+        InodeType::Dir => {
+            return_errno_with_message!(Errno::EPERM, "directories cannot be created by mknod")
+        }
+        InodeType::SymLink => {
+            return_errno_with_message!(Errno::EINVAL, "invalid file type")
         }
-        _ => return_errno_with_message!(Errno::EPERM, "unimplemented file types"),
     }
 
     Ok(SyscallReturn::Return(0))
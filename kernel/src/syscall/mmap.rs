@@ -13,6 +13,7 @@ use crate::{
     },
     prelude::*,
     vm::{
+        hugetlb::{self, HugePagePool, HUGETLB_FLAG_ENCODE_MASK, HUGETLB_FLAG_ENCODE_SHIFT},
         perms::VmPerms,
         vmar::is_userspace_vaddr,
         vmo::{VmoOptions, VmoRightsOp},
@@ -69,7 +70,18 @@ fn do_sys_mmap(
         return_errno_with_message!(Errno::ENOMEM, "mmap len too large");
     }
 
-    let len = len.align_up(PAGE_SIZE);
+    let len = match option.huge_pool {
+        Some(pool) if option.flags.contains(MMapFlags::MAP_ANONYMOUS) => {
+            if option.flags.contains(MMapFlags::MAP_FIXED) && addr % pool.page_size() != 0 {
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "the fixed address of huge pages must be aligned to the huge page size"
+                );
+            }
+            len.align_up(pool.page_size())
+        }
+        _ => len.align_up(PAGE_SIZE),
+    };
 
     if offset % PAGE_SIZE != 0 {
         return_errno_with_message!(Errno::EINVAL, "mmap only support page-aligned offset");
@@ -103,10 +115,14 @@ fn do_sys_mmap(
             warn!("MAP_32BIT is not supported");
         }
 
-        if option.typ() == MMapType::Shared {
+        if option.is_shared() {
             options = options.is_shared(true);
         }
 
+        if flags.contains(MMapFlags::MAP_NORESERVE) {
+            options = options.no_reserve();
+        }
+
         if option.flags.contains(MMapFlags::MAP_ANONYMOUS) {
             if offset != 0 {
                 return_errno_with_message!(
@@ -115,8 +131,15 @@ fn do_sys_mmap(
                 );
             }
 
-            // Anonymous shared mapping should share the same memory pages.
-            if option.typ() == MMapType::Shared {
+            if let Some(pool) = option.huge_pool {
+                // The huge pages are charged to the pool instead of the committed memory.
+                //
+                // TODO: A write access to a private mapping copies the huge page into base pages.
+                // Allocate the huge pages on the page faults of the private mapping instead.
+                let huge_vmo = pool.alloc_vmo(len, None)?;
+                options = options.vmo(huge_vmo).no_reserve();
+            } else if option.is_shared() {
+                // Anonymous shared mapping should share the same memory pages.
                 let shared_vmo = {
                     let vmo_options: VmoOptions<Rights> = VmoOptions::new(len);
                     vmo_options.alloc()?
@@ -124,36 +147,32 @@ fn do_sys_mmap(
                 options = options.vmo(shared_vmo);
             }
         } else {
-            let vmo = {
-                let mut file_table = ctx.thread_local.borrow_file_table_mut();
-                let file = get_file_fast!(&mut file_table, fd);
-                let inode_handle = file.as_inode_or_err()?;
-
-                let access_mode = inode_handle.access_mode();
-                if vm_perms.contains(VmPerms::READ) && !access_mode.is_readable() {
-                    return_errno!(Errno::EACCES);
-                }
-                if option.typ() == MMapType::Shared
-                    && vm_perms.contains(VmPerms::WRITE)
-                    && !access_mode.is_writable()
-                {
-                    return_errno!(Errno::EACCES);
-                }
-
-                let inode = inode_handle.dentry().inode();
-                inode
-                    .page_cache()
-                    .ok_or(Error::with_message(
-                        Errno::EBADF,
-                        "File does not have page cache",
-                    ))?
-                    .to_dyn()
-            };
-
-            options = options
-                .vmo(vmo)
-                .vmo_offset(offset)
-                .handle_page_faults_around();
+            let mut file_table = ctx.thread_local.borrow_file_table_mut();
+            let file = get_file_fast!(&mut file_table, fd);
+
+            let access_mode = file.access_mode();
+            if vm_perms.contains(VmPerms::READ) && !access_mode.is_readable() {
+                return_errno!(Errno::EACCES);
+            }
+            if option.is_shared() && vm_perms.contains(VmPerms::WRITE) && !access_mode.is_writable()
+            {
+                return_errno!(Errno::EACCES);
+            }
+
+            if let Ok(inode_handle) = file.as_inode_or_err()
+                && let Some(page_cache) = inode_handle.dentry().inode().page_cache()
+            {
+                options = options
+                    .vmo(page_cache.to_dyn())
+                    .mapped_file(inode_handle.dentry().clone())
+                    .vmo_offset(offset)
+                    .handle_page_faults_around();
+            } else {
+                // The files without page caches, e.g., io_uring instances and some devices,
+                // provide their own VMOs.
+                let (vmo, vmo_offset) = file.mmap_vmo(offset, len)?;
+                options = options.vmo(vmo).vmo_offset(vmo_offset);
+            }
         }
 
         options
@@ -216,6 +235,8 @@ bitflags! {
 pub struct MMapOptions {
     typ: MMapType,
     flags: MMapFlags,
+    /// The pool of the huge pages if `MAP_HUGETLB` is specified.
+    huge_pool: Option<&'static HugePagePool>,
 }
 
 impl TryFrom<u32> for MMapOptions {
@@ -225,11 +246,22 @@ impl TryFrom<u32> for MMapOptions {
         let typ_raw = (value & MAP_TYPE) as u8;
         let typ = MMapType::try_from(typ_raw)?;
 
-        let flags_raw = value & !MAP_TYPE;
+        let mut flags_raw = value & !MAP_TYPE;
+        let mut huge_pool = None;
+        if flags_raw & MMapFlags::MAP_HUGETLB.bits() != 0 {
+            // The huge page size is encoded in the high bits of the flags.
+            huge_pool = Some(hugetlb::pool_of_flags(flags_raw)?);
+            flags_raw &= !(HUGETLB_FLAG_ENCODE_MASK << HUGETLB_FLAG_ENCODE_SHIFT);
+        }
+
         let Some(flags) = MMapFlags::from_bits(flags_raw) else {
             return Err(Error::with_message(Errno::EINVAL, "unknown mmap flags"));
         };
-        Ok(MMapOptions { typ, flags })
+        Ok(MMapOptions {
+            typ,
+            flags,
+            huge_pool,
+        })
     }
 }
 
@@ -241,4 +273,9 @@ impl MMapOptions {
     pub fn flags(&self) -> MMapFlags {
         self.flags
     }
+
+    /// Returns whether the mapping is shared, i.e., `MAP_SHARED` or `MAP_SHARED_VALIDATE`.
+    pub fn is_shared(&self) -> bool {
+        matches!(self.typ, MMapType::Shared | MMapType::SharedValidate)
+    }
 }
@@ -2,18 +2,25 @@
 
 //! Read the Cpu ctx content then dispatch syscall to corresponding handler
 //! The each sub module contains functions that handle real syscall logic.
+use aster_trace::{declare_tracepoint, tracepoint};
 pub use clock_gettime::ClockId;
+pub use open::do_openat;
 use ostd::cpu::context::UserContext;
+pub use statx::do_statx;
 pub use timer_create::create_timer;
 
-use crate::{context::Context, cpu::LinuxAbi, prelude::*};
+use crate::{context::Context, cpu::LinuxAbi, prelude::*, process::ptrace as process_ptrace};
 
 mod accept;
 mod access;
+mod add_key;
+mod adjtimex;
 mod alarm;
 mod arch;
 mod arch_prctl;
+mod audit;
 mod bind;
+mod bpf;
 mod brk;
 mod capget;
 mod capset;
@@ -22,6 +29,7 @@ mod chmod;
 mod chown;
 mod chroot;
 mod clock_gettime;
+mod clock_settime;
 mod clone;
 mod close;
 mod connect;
@@ -32,6 +40,7 @@ mod eventfd;
 mod execve;
 mod exit;
 mod exit_group;
+mod fadvise64;
 mod fallocate;
 mod fcntl;
 mod flock;
@@ -39,6 +48,7 @@ mod fork;
 mod fsync;
 mod futex;
 mod get_priority;
+mod get_robust_list;
 mod getcpu;
 mod getcwd;
 mod getdents64;
@@ -62,7 +72,11 @@ mod gettid;
 mod gettimeofday;
 mod getuid;
 mod getxattr;
+mod inotify;
+mod io_uring;
 mod ioctl;
+mod ioprio;
+mod kcmp;
 mod kill;
 mod link;
 mod listen;
@@ -87,10 +101,12 @@ mod pread64;
 mod preadv;
 mod prlimit64;
 mod pselect6;
+mod ptrace;
 mod pwrite64;
 mod pwritev;
 mod read;
 mod readlink;
+mod reboot;
 mod recvfrom;
 mod recvmsg;
 mod removexattr;
@@ -99,6 +115,7 @@ mod rmdir;
 mod rt_sigaction;
 mod rt_sigpending;
 mod rt_sigprocmask;
+mod rt_sigqueueinfo;
 mod rt_sigreturn;
 mod rt_sigsuspend;
 mod sched_affinity;
@@ -111,6 +128,7 @@ mod sched_setattr;
 mod sched_setparam;
 mod sched_setscheduler;
 mod sched_yield;
+mod seccomp;
 mod select;
 mod semctl;
 mod semget;
@@ -125,6 +143,7 @@ mod setfsgid;
 mod setfsuid;
 mod setgid;
 mod setgroups;
+mod sethostname;
 mod setitimer;
 mod setpgid;
 mod setregid;
@@ -133,8 +152,13 @@ mod setresuid;
 mod setreuid;
 mod setsid;
 mod setsockopt;
+mod settimeofday;
 mod setuid;
 mod setxattr;
+mod shmat;
+mod shmctl;
+mod shmdt;
+mod shmget;
 mod shutdown;
 mod sigaltstack;
 mod signalfd;
@@ -146,6 +170,7 @@ mod statx;
 mod symlink;
 mod sync;
 mod sysinfo;
+mod syslog;
 mod tgkill;
 mod time;
 mod timer_create;
@@ -158,6 +183,7 @@ mod umask;
 mod umount;
 mod uname;
 mod unlink;
+mod unshare;
 mod utimens;
 mod wait4;
 mod waitid;
@@ -301,7 +327,8 @@ macro_rules! impl_syscall_nums_and_dispatch_fn {
                     }
                 )*
                 _ => {
-                    log::warn!("Unimplemented syscall number: {}", syscall_number);
+                    aster_logger::warn_ratelimited!("Unimplemented syscall number: {}", syscall_number);
+                    $crate::syscall::audit::report_missing_syscall(syscall_number, &args, ctx);
                     $crate::return_errno_with_message!($crate::error::Errno::ENOSYS, "Syscall was unimplemented");
                 }
             }
@@ -339,27 +366,80 @@ impl SyscallArgument {
     }
 }
 
+declare_tracepoint! {
+    /// Emitted when a system call is entered.
+    static SYS_ENTER: raw_syscalls:sys_enter(
+        tid,
+        nr,
+        arg0: hex,
+        arg1: hex,
+        arg2: hex,
+        arg3: hex,
+        arg4: hex,
+        arg5: hex,
+    );
+    /// Emitted when a system call returns.
+    ///
+    /// The system calls that do not return to the caller (e.g., `execve` on success) do not
+    /// emit this event.
+    static SYS_EXIT: raw_syscalls:sys_exit(tid, nr, ret: signed);
+}
+
+pub(crate) fn init() {
+    audit::init();
+    aster_trace::register_tracepoints(&[&SYS_ENTER, &SYS_EXIT]);
+}
+
 pub fn handle_syscall(ctx: &Context, user_ctx: &mut UserContext) {
+    if !process_ptrace::stop_at_syscall_entry(ctx, user_ctx) {
+        // The tracer has skipped the system call, so the system call number is reported as `-1`.
+        process_ptrace::stop_at_syscall_exit(ctx, user_ctx, usize::MAX);
+        return;
+    }
+
     let syscall_frame = SyscallArgument::new_from_context(user_ctx);
-    let syscall_return = arch::syscall_dispatch(
+    let [arg0, arg1, arg2, arg3, arg4, arg5] = syscall_frame.args;
+    tracepoint!(
+        SYS_ENTER,
+        ctx.posix_thread.tid(),
         syscall_frame.syscall_number,
-        syscall_frame.args,
-        ctx,
-        user_ctx,
+        arg0,
+        arg1,
+        arg2,
+        arg3,
+        arg4,
+        arg5
     );
 
-    match syscall_return {
-        Ok(return_value) => {
-            if let SyscallReturn::Return(return_value) = return_value {
-                user_ctx.set_syscall_ret(return_value as usize);
-            }
-        }
+    let syscall_return = match seccomp::check_syscall(&syscall_frame, ctx, user_ctx) {
+        Some(syscall_return) => Ok(syscall_return),
+        None => arch::syscall_dispatch(
+            syscall_frame.syscall_number,
+            syscall_frame.args,
+            ctx,
+            user_ctx,
+        ),
+    };
+
+    let return_value = match syscall_return {
+        Ok(SyscallReturn::Return(return_value)) => Some(return_value),
+        Ok(SyscallReturn::NoReturn) => None,
         Err(err) => {
             debug!("syscall return error: {:?}", err);
-            let errno = err.error() as i32;
-            user_ctx.set_syscall_ret((-errno) as usize)
+            Some(-(err.error() as i32 as isize))
         }
+    };
+    if let Some(return_value) = return_value {
+        user_ctx.set_syscall_ret(return_value as usize);
+        tracepoint!(
+            SYS_EXIT,
+            ctx.posix_thread.tid(),
+            syscall_frame.syscall_number,
+            return_value
+        );
     }
+
+    process_ptrace::stop_at_syscall_exit(ctx, user_ctx, syscall_frame.syscall_number);
 }
 
 #[macro_export]
@@ -382,7 +462,3 @@ macro_rules! log_syscall_entry {
         }
     };
 }
-
-pub(super) fn init() {
-    uname::init();
-}
@@ -1,17 +1,27 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use aster_block::BlockDevice;
+
 use super::SyscallReturn;
 use crate::{
+    device,
     fs::{
+        cgroupfs,
+        device::DeviceId,
         exfat::{ExfatFS, ExfatMountOptions},
         ext2::Ext2,
         fs_resolver::{FsPath, AT_FDCWD},
+        nfs::NfsFs,
         overlayfs::OverlayFS,
         path::Dentry,
-        utils::{FileSystem, InodeType},
+        ramfs::RamFS,
+        tracefs,
+        utils::{FileSystem, InodeMode, InodeType},
     },
     prelude::*,
+    process::credentials::capabilities::CapSet,
     syscall::constants::MAX_FILENAME_LEN,
+    vm::hugetlb::{self, HugePageSubpool},
 };
 
 /// The `data` argument is interpreted by the different filesystems.
@@ -35,13 +45,20 @@ pub fn sys_mount(
         devname, dirname, fstype_addr, mount_flags, data,
     );
 
+    if !ctx.posix_thread.credentials().has_capability(CapSet::SYS_ADMIN) {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "the current thread does not have the CAP_SYS_ADMIN capability"
+        );
+    }
+
     let dst_dentry = {
         let dirname = dirname.to_string_lossy();
         if dirname.is_empty() {
             return_errno_with_message!(Errno::ENOENT, "dirname is empty");
         }
         let fs_path = FsPath::new(AT_FDCWD, dirname.as_ref())?;
-        ctx.posix_thread.fs().resolver().read().lookup(&fs_path)?
+        ctx.posix_thread.fs().read().resolver().read().lookup(&fs_path)?
     };
 
     if mount_flags.contains(MountFlags::MS_REMOUNT) && mount_flags.contains(MountFlags::MS_BIND) {
@@ -94,7 +111,7 @@ fn do_bind_mount(
             return_errno_with_message!(Errno::ENOENT, "src_name is empty");
         }
         let fs_path = FsPath::new(AT_FDCWD, src_name.as_ref())?;
-        ctx.posix_thread.fs().resolver().read().lookup(&fs_path)?
+        ctx.posix_thread.fs().read().resolver().read().lookup(&fs_path)?
     };
 
     if src_dentry.type_() != InodeType::Dir {
@@ -117,7 +134,7 @@ fn do_move_mount_old(src_name: CString, dst_dentry: Dentry, ctx: &Context) -> Re
             return_errno_with_message!(Errno::ENOENT, "src_name is empty");
         }
         let fs_path = FsPath::new(AT_FDCWD, src_name.as_ref())?;
-        ctx.posix_thread.fs().resolver().read().lookup(&fs_path)?
+        ctx.posix_thread.fs().read().resolver().read().lookup(&fs_path)?
     };
 
     if !src_dentry.is_root_of_mount() {
@@ -153,6 +170,27 @@ fn do_new_mount(
     Ok(())
 }
 
+/// Looks up the block device by `devname`, which is either the path of a block device node (e.g.,
+/// `/dev/vda1`) or the name of a registered block device (e.g., `vext2`).
+fn lookup_block_device(devname: &CString, ctx: &Context) -> Result<Arc<dyn BlockDevice>> {
+    let devname = devname.to_string_lossy();
+    if !devname.contains('/') {
+        if let Some(device) = aster_block::get_device(devname.as_ref()) {
+            return Ok(device);
+        }
+    }
+
+    let fs_path = FsPath::new(AT_FDCWD, devname.as_ref())?;
+    let dentry = ctx.posix_thread.fs().read().resolver().read().lookup(&fs_path)?;
+    if dentry.type_() != InodeType::BlockDevice {
+        return_errno_with_message!(Errno::ENOTBLK, "the device is not a block device");
+    }
+    device::get_block_device(DeviceId::from(dentry.metadata().rdev)).ok_or(Error::with_message(
+        Errno::ENXIO,
+        "the block device does not exist",
+    ))
+}
+
 /// Get the filesystem by fs_type and devname.
 fn get_fs(
     fs_type: CString,
@@ -161,25 +199,35 @@ fn get_fs(
     ctx: &Context,
 ) -> Result<Arc<dyn FileSystem>> {
     let user_space = ctx.user_space();
-    let data = user_space.read_cstring(data, MAX_FILENAME_LEN)?;
+    let data = if data == 0 {
+        CString::default()
+    } else {
+        user_space.read_cstring(data, MAX_FILENAME_LEN)?
+    };
     let data = data.to_string_lossy();
 
     let fs_type = fs_type.to_str().unwrap();
     match fs_type {
         "ext2" => {
-            let device = aster_block::get_device(devname.to_str().unwrap()).ok_or(
-                Error::with_message(Errno::ENOENT, "device for ext2 does not exist"),
-            )?;
+            let device = lookup_block_device(&devname, ctx)?;
             let ext2_fs = Ext2::open(device)?;
             Ok(ext2_fs)
         }
         "exfat" => {
-            let device = aster_block::get_device(devname.to_str().unwrap()).ok_or(
-                Error::with_message(Errno::ENOENT, "device for exfat does not exist"),
-            )?;
+            let device = lookup_block_device(&devname, ctx)?;
             let exfat_fs = ExfatFS::open(device, ExfatMountOptions::default())?;
             Ok(exfat_fs)
         }
+        "nfs" => {
+            let nfs_fs = NfsFs::mount(devname.to_str()?, data.as_ref())?;
+            Ok(nfs_fs)
+        }
+        "cgroup2" => Ok(cgroupfs::singleton().clone()),
+        "tracefs" => Ok(tracefs::singleton().clone()),
+        "tmpfs" | "ramfs" => {
+            let ram_fs = create_ramfs(fs_type, data.as_ref())?;
+            Ok(ram_fs)
+        }
         "overlay" => {
             let overlay_fs = create_overlayfs(data.as_ref(), ctx)?;
             Ok(overlay_fs)
@@ -188,6 +236,35 @@ fn get_fs(
     }
 }
 
+/// Creates a RamFS, which also serves as tmpfs since the pages are never swapped out.
+///
+/// Only the `mode` option, which is the permission mode of the root directory in octal, is
+/// supported. The other options (e.g., `size`) are ignored.
+fn create_ramfs(fs_type: &str, data: &str) -> Result<Arc<RamFS>> {
+    // Like Linux, the root directory of tmpfs is world-writable and sticky by default.
+    let mut mode = if fs_type == "tmpfs" { 0o1777 } else { 0o755 };
+
+    for entry in data.split(',').filter(|entry| !entry.is_empty()) {
+        let (key, value) = entry.split_once('=').unwrap_or((entry, ""));
+        if key == "mode" {
+            mode = u16::from_str_radix(value, 8)
+                .map_err(|_| Error::with_message(Errno::EINVAL, "the mode option is invalid"))?;
+        } else {
+            warn!("unsupported {} option: {}", fs_type, entry);
+        }
+    }
+        "hugetlbfs" => {
+            let hugetlb_fs = create_hugetlbfs(data.as_ref())?;
+            Ok(hugetlb_fs)
+        }
+
+    let ram_fs = RamFS::new();
+    ram_fs
+        .root_inode()
+        .set_mode(InodeMode::from_bits_truncate(mode))?;
+    Ok(ram_fs)
+}
+
 // TODO: Support read-only mount (no upper) and customized features
 fn create_overlayfs(data: &str, ctx: &Context) -> Result<Arc<OverlayFS>> {
     let mut lower = Vec::new();
@@ -213,6 +290,75 @@ fn create_overlayfs(data: &str, ctx: &Context) -> Result<Arc<OverlayFS>> {
                 }
             }
             (Some("workdir"), Some(path)) => {
+/// Creates a hugetlbfs, whose regular files are backed by huge pages.
+///
+/// The supported options are:
+/// - `pagesize`, the size of the huge pages, which is the default huge page size if omitted;
+/// - `size`, the maximum size of the huge pages used by the file system;
+/// - `min_size`, the size of the huge pages reserved for the file system;
+/// - `mode`, the permission mode of the root directory in octal.
+///
+/// The sizes are in bytes with an optional suffix `K`, `M`, or `G`, or in percentage of the huge
+/// page pool with a suffix `%`.
+fn create_hugetlbfs(data: &str) -> Result<Arc<RamFS>> {
+    let mut page_size = 0;
+    let mut max_size = None;
+    let mut min_size = None;
+    let mut mode = 0o755;
+
+    for entry in data.split(',').filter(|entry| !entry.is_empty()) {
+        let (key, value) = entry.split_once('=').unwrap_or((entry, ""));
+        match key {
+            "pagesize" => page_size = parse_mount_size(value)?,
+            "size" => max_size = Some(value),
+            "min_size" => min_size = Some(value),
+            "mode" => {
+                mode = u16::from_str_radix(value, 8).map_err(|_| {
+                    Error::with_message(Errno::EINVAL, "the mode option is invalid")
+                })?;
+            }
+            _ => warn!("unsupported hugetlbfs option: {}", entry),
+        }
+    }
+
+    let pool = hugetlb::pool_of_size(page_size)?;
+    let pool_size = pool.nr_pages() * pool.page_size();
+    let parse_size = |value: &str| match value.strip_suffix('%') {
+        Some(percent) => percent
+            .parse::<usize>()
+            .map(|percent| pool_size / 100 * percent)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the size option is invalid")),
+        None => parse_mount_size(value),
+    };
+    let max_size = max_size.map(parse_size).transpose()?;
+    let min_size = min_size.map(parse_size).transpose()?.unwrap_or(0);
+
+    let subpool = HugePageSubpool::new(pool, max_size, min_size)?;
+    let hugetlb_fs = RamFS::new_hugetlbfs(subpool);
+    hugetlb_fs
+        .root_inode()
+        .set_mode(InodeMode::from_bits_truncate(mode))?;
+    Ok(hugetlb_fs)
+}
+
+/// Parses a size in bytes with an optional suffix `K`, `M`, or `G`.
+fn parse_mount_size(value: &str) -> Result<usize> {
+    let (number, shift) = match value.as_bytes().last() {
+        Some(b'k' | b'K') => (&value[..value.len() - 1], 10),
+        Some(b'm' | b'M') => (&value[..value.len() - 1], 20),
+        Some(b'g' | b'G') => (&value[..value.len() - 1], 30),
+        _ => (value, 0),
+    };
+    number
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| number.checked_mul(1 << shift))
+        .ok_or(Error::with_message(
+            Errno::EINVAL,
+            "the size option is invalid",
+        ))
+}
+
                 if path.is_empty() {
                     return_errno_with_message!(Errno::ENOENT, "workdir is empty");
                 }
@@ -222,14 +368,35 @@ fn create_overlayfs(data: &str, ctx: &Context) -> Result<Arc<OverlayFS>> {
         }
     }
 
-    let fs = ctx.posix_thread.fs().resolver().read();
+    if lower.is_empty() {
+        return_errno_with_message!(Errno::EINVAL, "lowerdir is required");
+    }
+    if upper.is_empty() || work.is_empty() {
+        return_errno_with_message!(Errno::EINVAL, "upperdir and workdir are required");
+    }
+
+    let fs_ref = ctx.posix_thread.fs().read();
+    let fs = fs_ref.resolver().read();
 
-    let upper = fs.lookup(&FsPath::new(AT_FDCWD, upper)?)?;
+    let lookup_dir = |path: &str| -> Result<Dentry> {
+        let dentry = fs.lookup(&FsPath::new(AT_FDCWD, path)?)?;
+        if dentry.type_() != InodeType::Dir {
+            return_errno_with_message!(Errno::EINVAL, "the layer is not a directory");
+        }
+        Ok(dentry)
+    };
+    let upper = lookup_dir(upper)?;
     let lower = lower
         .iter()
-        .map(|lower| fs.lookup(&FsPath::new(AT_FDCWD, lower).unwrap()).unwrap())
-        .collect();
-    let work = fs.lookup(&FsPath::new(AT_FDCWD, work)?)?;
+        .map(|lower| lookup_dir(lower))
+        .collect::<Result<Vec<_>>>()?;
+    let work = lookup_dir(work)?;
+    if !Arc::ptr_eq(&upper.fs(), &work.fs()) {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "workdir and upperdir must reside in the same file system"
+        );
+    }
 
     let overlayfs = OverlayFS::new(upper, lower, work)?;
     Ok(overlayfs)
@@ -1,9 +1,54 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use align_ext::AlignExt;
+
 use super::SyscallReturn;
 use crate::prelude::*;
 
-pub fn sys_msync(_start: Vaddr, _size: usize, _flag: i32, _ctx: &Context) -> Result<SyscallReturn> {
-    // TODO: implement real `msync`.
+pub fn sys_msync(start: Vaddr, size: usize, flag: i32, ctx: &Context) -> Result<SyscallReturn> {
+    let flags = MsyncFlags::from_bits(flag)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid flags"))?;
+    debug!(
+        "start = 0x{:x}, size = 0x{:x}, flags = {:?}",
+        start, size, flags
+    );
+
+    if start % PAGE_SIZE != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the start address should be page aligned");
+    }
+    if flags.contains(MsyncFlags::MS_ASYNC | MsyncFlags::MS_SYNC) {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "MS_ASYNC and MS_SYNC cannot be specified together"
+        );
+    }
+    if size > isize::MAX as usize {
+        return_errno_with_message!(Errno::ENOMEM, "size align overflow");
+    }
+
+    let size = size.align_up(PAGE_SIZE);
+    let end = start.checked_add(size).ok_or(Error::with_message(
+        Errno::ENOMEM,
+        "integer overflow when (start + size)",
+    ))?;
+    if start == end {
+        return Ok(SyscallReturn::Return(0));
+    }
+
+    // The dirty pages are always marked in the page cache, so that they will be written back
+    // eventually. Like Linux, only `MS_SYNC` waits for the writeback. `MS_INVALIDATE` needs
+    // nothing to do because the mappings share the same pages with the page cache.
+    let user_space = ctx.user_space();
+    let root_vmar = user_space.root_vmar();
+    root_vmar.sync(start..end, flags.contains(MsyncFlags::MS_SYNC))?;
+
     Ok(SyscallReturn::Return(0))
 }
+
+bitflags! {
+    struct MsyncFlags: i32 {
+        const MS_ASYNC = 1;
+        const MS_INVALIDATE = 2;
+        const MS_SYNC = 4;
+    }
+}
@@ -3,7 +3,7 @@
 use super::SyscallReturn;
 use crate::{
     fs::{
-        file_table::{FdFlags, FileDesc},
+        file_table::{FdCreationFlags, FileDesc},
         fs_resolver::{FsPath, AT_FDCWD},
         utils::{AccessMode, CreationFlags},
     },
@@ -24,36 +24,45 @@ pub fn sys_openat(
         dirfd, path, flags, mode
     );
 
+    let fd =
+        do_openat(dirfd, &path.to_string_lossy(), flags, mode, ctx).map_err(|err| {
+            match err.error() {
+                Errno::EINTR => Error::new(Errno::ERESTARTSYS),
+                _ => err,
+            }
+        })?;
+    Ok(SyscallReturn::Return(fd as _))
+}
+
+/// Opens the file at `path` relative to `dirfd`, returning the new file descriptor.
+pub fn do_openat(
+    dirfd: FileDesc,
+    path: &str,
+    flags: u32,
+    mode: u16,
+    ctx: &Context,
+) -> Result<FileDesc> {
     let current = ctx.posix_thread;
     let file_handle = {
-        let path = path.to_string_lossy();
-        let fs_path = FsPath::new(dirfd, path.as_ref())?;
-        let mask_mode = mode & !current.fs().umask().read().get();
+        let fs_path = FsPath::new(dirfd, path)?;
+        let mask_mode = mode & !current.fs().read().umask().read().get();
         let inode_handle = current
             .fs()
+            .read()
             .resolver()
             .read()
-            .open(&fs_path, flags, mask_mode)
-            .map_err(|err| match err.error() {
-                Errno::EINTR => Error::new(Errno::ERESTARTSYS),
-                _ => err,
-            })?;
+            .open(&fs_path, flags, mask_mode)?;
         Arc::new(inode_handle)
     };
 
     let fd = {
         let file_table = ctx.thread_local.borrow_file_table();
         let mut file_table_locked = file_table.unwrap().write();
-        let fd_flags =
-            if CreationFlags::from_bits_truncate(flags).contains(CreationFlags::O_CLOEXEC) {
-                FdFlags::CLOEXEC
-            } else {
-                FdFlags::empty()
-            };
-        file_table_locked.insert(file_handle, fd_flags)
+        let fd_flags = FdCreationFlags::from_bits_truncate(flags).fd_flags();
+        file_table_locked.insert(file_handle, fd_flags)?
     };
 
-    Ok(SyscallReturn::Return(fd as _))
+    Ok(fd)
 }
 
 pub fn sys_open(path_addr: Vaddr, flags: u32, mode: u16, ctx: &Context) -> Result<SyscallReturn> {
@@ -3,9 +3,10 @@
 use super::SyscallReturn;
 use crate::{
     fs::{
-        file_table::{FdFlags, FileDesc},
+        file_handle::FileLike,
+        file_table::{FdCreationFlags, FileDesc},
         pipe,
-        utils::CreationFlags,
+        utils::StatusFlags,
     },
     prelude::*,
 };
@@ -13,20 +14,32 @@ use crate::{
 pub fn sys_pipe2(fds: Vaddr, flags: u32, ctx: &Context) -> Result<SyscallReturn> {
     debug!("flags: {:?}", flags);
 
-    let (pipe_reader, pipe_writer) = pipe::new_pair()?;
+    if flags & !(FdCreationFlags::all().bits() | StatusFlags::O_DIRECT.bits()) != 0 {
+        return_errno_with_message!(Errno::EINVAL, "invalid flags");
+    }
+    let is_packet_mode = StatusFlags::from_bits_truncate(flags).contains(StatusFlags::O_DIRECT);
+    let flags = FdCreationFlags::from_bits_truncate(flags);
 
-    let fd_flags = if CreationFlags::from_bits_truncate(flags).contains(CreationFlags::O_CLOEXEC) {
-        FdFlags::CLOEXEC
-    } else {
-        FdFlags::empty()
-    };
+    let (pipe_reader, pipe_writer) = pipe::new_pair()?;
+    if is_packet_mode {
+        pipe_reader.set_status_flags(StatusFlags::O_DIRECT)?;
+        pipe_writer.set_status_flags(StatusFlags::O_DIRECT)?;
+    }
+    let fd_flags = flags.apply_to(pipe_reader.as_ref())?;
+    flags.apply_to(pipe_writer.as_ref())?;
 
     let file_table = ctx.thread_local.borrow_file_table();
     let mut file_table_locked = file_table.unwrap().write();
 
+    let reader_fd = file_table_locked.insert(pipe_reader, fd_flags)?;
+    let writer_fd = file_table_locked
+        .insert(pipe_writer, fd_flags)
+        .inspect_err(|_| {
+            file_table_locked.close_file(reader_fd).unwrap();
+        })?;
     let pipe_fds = PipeFds {
-        reader_fd: file_table_locked.insert(pipe_reader, fd_flags),
-        writer_fd: file_table_locked.insert(pipe_writer, fd_flags),
+        reader_fd,
+        writer_fd,
     };
     debug!("pipe_fds: {:?}", pipe_fds);
 
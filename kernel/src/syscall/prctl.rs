@@ -1,9 +1,18 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use super::SyscallReturn;
+use ostd::mm::MAX_USERSPACE_VADDR;
+
+use super::{seccomp::set_mode_filter, SyscallReturn};
 use crate::{
+    fs::{
+        file_table::{get_file_fast, FileDesc},
+        utils::InodeType,
+    },
     prelude::*,
-    process::{posix_thread::MAX_THREAD_NAME_LEN, signal::sig_num::SigNum},
+    process::{
+        credentials::capabilities::CapSet, posix_thread::MAX_THREAD_NAME_LEN, seccomp::SeccompMode,
+        signal::sig_num::SigNum,
+    },
 };
 
 pub fn sys_prctl(
@@ -93,11 +102,78 @@ pub fn sys_prctl(
             ctx.user_space()
                 .write_val(write_addr, &(process.is_child_subreaper() as u32))?;
         }
+        PrctlCmd::PR_GET_SECCOMP => {
+            let mode = ctx.posix_thread.seccomp().mode();
+            return Ok(SyscallReturn::Return(mode as _));
+        }
+        PrctlCmd::PR_SET_SECCOMP(mode, fprog_addr) => match mode {
+            SeccompMode::Strict => ctx.posix_thread.seccomp().set_mode_strict()?,
+            SeccompMode::Filter => return set_mode_filter(0, fprog_addr, ctx),
+            SeccompMode::Disabled => {
+                return_errno_with_message!(Errno::EINVAL, "seccomp cannot be disabled")
+            }
+        },
+        PrctlCmd::PR_GET_NO_NEW_PRIVS => {
+            let no_new_privs = ctx.posix_thread.no_new_privs();
+            return Ok(SyscallReturn::Return(no_new_privs as _));
+        }
+        PrctlCmd::PR_SET_NO_NEW_PRIVS => {
+            ctx.posix_thread.set_no_new_privs();
+        }
+        PrctlCmd::PR_SET_MM(option, value) => set_mm(option, value, ctx)?,
         _ => todo!(),
     }
     Ok(SyscallReturn::Return(0))
 }
 
+/// Modifies the memory descriptor fields of the current process.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/kernel/sys.c#L2216>
+fn set_mm(option: SetMmOption, value: u64, ctx: &Context) -> Result<()> {
+    if !ctx
+        .posix_thread
+        .credentials()
+        .has_capability(CapSet::SYS_RESOURCE)
+    {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "modifying the memory descriptor requires CAP_SYS_RESOURCE"
+        );
+    }
+
+    if option == SetMmOption::ExeFile {
+        let fd = FileDesc::try_from(value)
+            .map_err(|_| Error::with_message(Errno::EBADF, "the file descriptor is invalid"))?;
+        let dentry = {
+            let mut file_table = ctx.thread_local.borrow_file_table_mut();
+            let file = get_file_fast!(&mut file_table, fd);
+            file.as_inode_or_err()?.dentry().clone()
+        };
+        if dentry.type_() != InodeType::File {
+            return_errno_with_message!(Errno::EACCES, "the executable file is not a regular file");
+        }
+        ctx.process.set_executable_path(dentry.abs_path());
+        return Ok(());
+    }
+
+    let addr = value as Vaddr;
+    if addr >= MAX_USERSPACE_VADDR {
+        return_errno_with_message!(Errno::EINVAL, "the address is not in the user space");
+    }
+
+    let heap = ctx.process.heap();
+    match option {
+        SetMmOption::StartBrk => heap.set_base_and_brk(Some(addr), None),
+        SetMmOption::Brk => heap.set_base_and_brk(None, Some(addr)),
+        // TODO: Support setting other fields. Currently, the code, data, stack, and argument
+        // areas are not recorded, but derived from the mappings or the initial stack.
+        _ => return_errno_with_message!(
+            Errno::EINVAL,
+            "the memory descriptor field cannot be modified"
+        ),
+    }
+}
+
 const PR_SET_PDEATHSIG: i32 = 1;
 const PR_GET_PDEATHSIG: i32 = 2;
 const PR_GET_DUMPABLE: i32 = 3;
@@ -106,10 +182,15 @@ const PR_GET_KEEPCAPS: i32 = 7;
 const PR_SET_KEEPCAPS: i32 = 8;
 const PR_SET_NAME: i32 = 15;
 const PR_GET_NAME: i32 = 16;
+const PR_GET_SECCOMP: i32 = 21;
+const PR_SET_SECCOMP: i32 = 22;
 const PR_SET_TIMERSLACK: i32 = 29;
 const PR_GET_TIMERSLACK: i32 = 30;
+const PR_SET_MM: i32 = 35;
 const PR_SET_CHILD_SUBREAPER: i32 = 36;
 const PR_GET_CHILD_SUBREAPER: i32 = 37;
+const PR_SET_NO_NEW_PRIVS: i32 = 38;
+const PR_GET_NO_NEW_PRIVS: i32 = 39;
 
 #[expect(non_camel_case_types)]
 #[derive(Debug, Clone, Copy)]
@@ -128,6 +209,11 @@ pub enum PrctlCmd {
     PR_GET_DUMPABLE,
     PR_SET_CHILD_SUBREAPER(bool),
     PR_GET_CHILD_SUBREAPER(Vaddr),
+    PR_GET_SECCOMP,
+    PR_SET_SECCOMP(SeccompMode, Vaddr),
+    PR_SET_NO_NEW_PRIVS,
+    PR_GET_NO_NEW_PRIVS,
+    PR_SET_MM(SetMmOption, u64),
 }
 
 #[repr(u64)]
@@ -138,8 +224,28 @@ pub enum Dumpable {
     Root = 2,    /* Dump as root */
 }
 
+#[repr(u64)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub enum SetMmOption {
+    StartCode = 1,
+    EndCode = 2,
+    StartData = 3,
+    EndData = 4,
+    StartStack = 5,
+    StartBrk = 6,
+    Brk = 7,
+    ArgStart = 8,
+    ArgEnd = 9,
+    EnvStart = 10,
+    EnvEnd = 11,
+    Auxv = 12,
+    ExeFile = 13,
+    Map = 14,
+    MapSize = 15,
+}
+
 impl PrctlCmd {
-    fn from_args(option: i32, arg2: u64, _arg3: u64, _arg4: u64, _arg5: u64) -> Result<PrctlCmd> {
+    fn from_args(option: i32, arg2: u64, arg3: u64, arg4: u64, arg5: u64) -> Result<PrctlCmd> {
         match option {
             PR_SET_PDEATHSIG => {
                 let signum = SigNum::try_from(arg2 as u8)?;
@@ -156,6 +262,30 @@ impl PrctlCmd {
             PR_SET_KEEPCAPS => Ok(PrctlCmd::PR_SET_KEEPCAPS(arg2 as _)),
             PR_SET_CHILD_SUBREAPER => Ok(PrctlCmd::PR_SET_CHILD_SUBREAPER(arg2 > 0)),
             PR_GET_CHILD_SUBREAPER => Ok(PrctlCmd::PR_GET_CHILD_SUBREAPER(arg2 as _)),
+            PR_GET_SECCOMP => Ok(PrctlCmd::PR_GET_SECCOMP),
+            PR_SET_SECCOMP => {
+                let mode = SeccompMode::try_from(u8::try_from(arg2)?)?;
+                Ok(PrctlCmd::PR_SET_SECCOMP(mode, arg3 as _))
+            }
+            PR_SET_NO_NEW_PRIVS => {
+                if arg2 != 1 || arg3 != 0 || arg4 != 0 || arg5 != 0 {
+                    return_errno_with_message!(Errno::EINVAL, "invalid arguments");
+                }
+                Ok(PrctlCmd::PR_SET_NO_NEW_PRIVS)
+            }
+            PR_GET_NO_NEW_PRIVS => {
+                if arg2 != 0 || arg3 != 0 || arg4 != 0 || arg5 != 0 {
+                    return_errno_with_message!(Errno::EINVAL, "invalid arguments");
+                }
+                Ok(PrctlCmd::PR_GET_NO_NEW_PRIVS)
+            }
+            PR_SET_MM => {
+                let option = SetMmOption::try_from(arg2)?;
+                if arg4 != 0 || arg5 != 0 {
+                    return_errno_with_message!(Errno::EINVAL, "invalid arguments");
+                }
+                Ok(PrctlCmd::PR_SET_MM(option, arg3))
+            }
             _ => {
                 debug!("prctl cmd number: {}", option);
                 return_errno_with_message!(Errno::EINVAL, "unsupported prctl command");
@@ -39,5 +39,6 @@ pub fn sys_pread64(
         file.read_at(offset as usize, &mut writer)?
     };
 
+    ctx.process.io_stats().account_read(read_len);
     Ok(SyscallReturn::Return(read_len as _))
 }
@@ -14,6 +14,7 @@ pub fn sys_readv(
     ctx: &Context,
 ) -> Result<SyscallReturn> {
     let res = do_sys_readv(fd, io_vec_ptr, io_vec_count, ctx)?;
+    ctx.process.io_stats().account_read(res);
     Ok(SyscallReturn::Return(res as _))
 }
 
@@ -24,7 +25,8 @@ pub fn sys_preadv(
     offset: i64,
     ctx: &Context,
 ) -> Result<SyscallReturn> {
-    let res = do_sys_preadv(fd, io_vec_ptr, io_vec_count, offset, RWFFlag::empty(), ctx)?;
+    let res = do_sys_preadv(fd, io_vec_ptr, io_vec_count, offset, ctx)?;
+    ctx.process.io_stats().account_read(res);
     Ok(SyscallReturn::Return(res as _))
 }
 
@@ -36,15 +38,15 @@ pub fn sys_preadv2(
     flags: u32,
     ctx: &Context,
 ) -> Result<SyscallReturn> {
-    let flags = match RWFFlag::from_bits(flags) {
-        Some(flags) => flags,
-        None => return_errno_with_message!(Errno::EINVAL, "invalid flags"),
-    };
-    let res = if offset == -1 {
-        do_sys_readv(fd, io_vec_ptr, io_vec_count, ctx)?
-    } else {
-        do_sys_preadv(fd, io_vec_ptr, io_vec_count, offset, flags, ctx)?
-    };
+    let flags = RWFFlag::from_user(flags)?;
+    let res = do_with_rwf_flags(flags, ctx, || {
+        if offset == -1 {
+            do_sys_readv(fd, io_vec_ptr, io_vec_count, ctx)
+        } else {
+            do_sys_preadv(fd, io_vec_ptr, io_vec_count, offset, ctx)
+        }
+    })?;
+    ctx.process.io_stats().account_read(res);
     Ok(SyscallReturn::Return(res as _))
 }
 
@@ -53,7 +55,6 @@ fn do_sys_preadv(
     io_vec_ptr: Vaddr,
     io_vec_count: usize,
     offset: i64,
-    _flags: RWFFlag,
     ctx: &Context,
 ) -> Result<usize> {
     debug!(
@@ -103,7 +104,12 @@ fn do_sys_preadv(
         // but the current implementation does not ensure atomicity.
         // A suitable fix would be to add a `readv` method for the `FileLike` trait,
         // allowing each subsystem to implement atomicity.
-        let read_len = file.read_at(cur_offset, writer)?;
+        let read_len = match file.read_at(cur_offset, writer) {
+            Ok(read_len) => read_len,
+            // Report the data that have been read, if any.
+            Err(_) if total_len > 0 => break,
+            Err(err) => return Err(err),
+        };
         total_len += read_len;
         cur_offset += read_len;
         if read_len == 0 || writer.has_avail() {
@@ -148,7 +154,12 @@ fn do_sys_readv(
         // but the current implementation does not ensure atomicity.
         // A suitable fix would be to add a `readv` method for the `FileLike` trait,
         // allowing each subsystem to implement atomicity.
-        let read_len = file.read(writer)?;
+        let read_len = match file.read(writer) {
+            Ok(read_len) => read_len,
+            // Report the data that have been read, if any.
+            Err(_) if total_len > 0 => break,
+            Err(err) => return Err(err),
+        };
         total_len += read_len;
         if read_len == 0 || writer.has_avail() {
             // End of file reached or no more data to read
@@ -160,10 +171,47 @@ fn do_sys_readv(
 }
 
 bitflags! {
-    struct RWFFlag: u32 {
-        const RWF_DSYNC = 0x00000001;
-        const RWF_HIPRI = 0x00000002;
+    /// The flags of `preadv2` and `pwritev2`.
+    pub(super) struct RWFFlag: u32 {
+        /// Polls for the high priority I/O, which is only a hint.
+        const RWF_HIPRI = 0x00000001;
+        /// Writes with the data integrity completion, like `O_DSYNC`.
+        const RWF_DSYNC = 0x00000002;
+        /// Writes with the file integrity completion, like `O_SYNC`.
         const RWF_SYNC = 0x00000004;
+        /// Fails with `EAGAIN` instead of blocking.
         const RWF_NOWAIT = 0x00000008;
     }
 }
+
+impl RWFFlag {
+    /// Parses the flags from the user.
+    ///
+    /// As in Linux, unknown flags are reported with `EOPNOTSUPP`, so that the user can probe
+    /// whether a flag is supported.
+    pub(super) fn from_user(flags: u32) -> Result<Self> {
+        Self::from_bits(flags)
+            .ok_or_else(|| Error::with_message(Errno::EOPNOTSUPP, "the flags are not supported"))
+    }
+}
+
+/// Performs the I/O operation with the flags.
+///
+/// With `RWF_NOWAIT`, the operation fails with `EAGAIN` if it would wait for the file to be
+/// ready. Note that the operation may still block on the underlying device, e.g., when reading
+/// a regular file that is not cached.
+pub(super) fn do_with_rwf_flags<R>(
+    flags: RWFFlag,
+    ctx: &Context,
+    op: impl FnOnce() -> Result<R>,
+) -> Result<R> {
+    if !flags.contains(RWFFlag::RWF_NOWAIT) {
+        return op();
+    }
+
+    let is_io_nowait = ctx.thread_local.is_io_nowait();
+    is_io_nowait.set(true);
+    let res = op();
+    is_io_nowait.set(false);
+    res
+}
@@ -0,0 +1,198 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_rights::Full;
+use int_to_c_enum::TryFromInt;
+
+use super::SyscallReturn;
+use crate::{
+    arch::ptrace::UserRegs,
+    prelude::*,
+    process::{
+        posix_thread::{thread_table, AsPosixThread},
+        ptrace::{self, PtraceOptions},
+        signal::sig_num::SigNum,
+    },
+    thread::{Thread, Tid},
+    vm::vmar::Vmar,
+};
+
+pub fn sys_ptrace(
+    request: u32,
+    pid: Tid,
+    addr: Vaddr,
+    data: usize,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let request = PtraceRequest::try_from(request)
+        .map_err(|_| Error::with_message(Errno::EIO, "the ptrace request is not supported"))?;
+    debug!(
+        "request = {:?}, pid = {}, addr = 0x{:x}, data = 0x{:x}",
+        request, pid, addr, data
+    );
+
+    if request == PtraceRequest::TraceMe {
+        ptrace::trace_me(ctx)?;
+        return Ok(SyscallReturn::Return(0));
+    }
+
+    let thread = thread_table::get_thread(pid)
+        .ok_or_else(|| Error::with_message(Errno::ESRCH, "the thread does not exist"))?;
+    if request == PtraceRequest::Attach {
+        ptrace::attach(&thread, ctx)?;
+        return Ok(SyscallReturn::Return(0));
+    }
+    if request == PtraceRequest::Seize {
+        if addr != 0 {
+            return_errno_with_message!(Errno::EIO, "the address must be zero");
+        }
+        // Like Linux, the invalid options are reported as `EIO` instead of `EINVAL`.
+        let options = parse_options(data)
+            .map_err(|_| Error::with_message(Errno::EIO, "the ptrace options are not supported"))?;
+        ptrace::seize(&thread, options, ctx)?;
+        return Ok(SyscallReturn::Return(0));
+    }
+
+    let tracee = thread.as_posix_thread().unwrap().tracee();
+    let tracer = ctx.process;
+    match request {
+        PtraceRequest::TraceMe | PtraceRequest::Attach | PtraceRequest::Seize => unreachable!(),
+        PtraceRequest::PeekText | PtraceRequest::PeekData => {
+            tracee.check_stopped(tracer)?;
+            let mut word = [0u8; size_of::<usize>()];
+            access_tracee_memory(&thread, |vmar| vmar.read_bytes(addr, &mut word))?;
+            // The raw system call stores the word at `data`. The C library returns the word.
+            ctx.user_space()
+                .write_val(data, &usize::from_ne_bytes(word))?;
+        }
+        PtraceRequest::PokeText | PtraceRequest::PokeData => {
+            tracee.check_stopped(tracer)?;
+            // TODO: Support writing to the read-only mappings (e.g., to set breakpoints in the
+            // code), which requires forcing the copy-on-write of the private mappings.
+            access_tracee_memory(&thread, |vmar| vmar.write_bytes(addr, &data.to_ne_bytes()))?;
+        }
+        PtraceRequest::Cont => tracee.resume(tracer, resume_signal(data)?, false)?,
+        PtraceRequest::Syscall => tracee.resume(tracer, resume_signal(data)?, true)?,
+        PtraceRequest::Detach => ptrace::detach(&thread, resume_signal(data)?, ctx)?,
+        #[cfg(target_arch = "x86_64")]
+        PtraceRequest::GetRegs => {
+            let regs = tracee.regs(tracer)?;
+            ctx.user_space().write_val(data, &regs)?;
+        }
+        #[cfg(target_arch = "x86_64")]
+        PtraceRequest::SetRegs => {
+            tracee.check_stopped(tracer)?;
+            let regs = ctx.user_space().read_val::<UserRegs>(data)?;
+            tracee.set_regs(tracer, &regs)?;
+        }
+        PtraceRequest::GetRegSet => {
+            check_regset(addr)?;
+            let regs = tracee.regs(tracer)?;
+            let user_space = ctx.user_space();
+            let mut iov = user_space.read_val::<iovec_t>(data)?;
+            iov.len = iov.len.min(size_of::<UserRegs>());
+            user_space.write_bytes(iov.base, &mut VmReader::from(&regs.as_bytes()[..iov.len]))?;
+            user_space.write_val(data, &iov)?;
+        }
+        PtraceRequest::SetRegSet => {
+            check_regset(addr)?;
+            let mut regs = tracee.regs(tracer)?;
+            let user_space = ctx.user_space();
+            let mut iov = user_space.read_val::<iovec_t>(data)?;
+            iov.len = iov.len.min(size_of::<UserRegs>());
+            user_space.read_bytes(
+                iov.base,
+                &mut VmWriter::from(&mut regs.as_bytes_mut()[..iov.len]),
+            )?;
+            tracee.set_regs(tracer, &regs)?;
+            user_space.write_val(data, &iov)?;
+        }
+        PtraceRequest::SetOptions => tracee.set_options(tracer, parse_options(data)?)?,
+        PtraceRequest::GetEventMsg => {
+            let event_msg = tracee.event_msg(tracer)?;
+            ctx.user_space().write_val(data, &event_msg)?;
+        }
+        PtraceRequest::GetSigInfo => {
+            let siginfo = tracee.siginfo(tracer)?;
+            ctx.user_space().write_val(data, &siginfo)?;
+        }
+        PtraceRequest::Interrupt => ptrace::interrupt(&thread, ctx)?,
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
+
+#[derive(Debug, Clone, Copy, TryFromInt, PartialEq)]
+#[repr(u32)]
+enum PtraceRequest {
+    TraceMe = 0,
+    PeekText = 1,
+    PeekData = 2,
+    PokeText = 4,
+    PokeData = 5,
+    Cont = 7,
+    #[cfg(target_arch = "x86_64")]
+    GetRegs = 12,
+    #[cfg(target_arch = "x86_64")]
+    SetRegs = 13,
+    Attach = 16,
+    Detach = 17,
+    Syscall = 24,
+    SetOptions = 0x4200,
+    GetEventMsg = 0x4201,
+    GetSigInfo = 0x4202,
+    GetRegSet = 0x4204,
+    SetRegSet = 0x4205,
+    Seize = 0x4206,
+    Interrupt = 0x4207,
+}
+
+/// The register set of the general-purpose registers.
+const NT_PRSTATUS: usize = 1;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct iovec_t {
+    base: Vaddr,
+    len: usize,
+}
+
+fn check_regset(regset: usize) -> Result<()> {
+    if regset != NT_PRSTATUS {
+        return_errno_with_message!(Errno::EINVAL, "the register set is not supported");
+    }
+    Ok(())
+}
+
+fn parse_options(data: usize) -> Result<PtraceOptions> {
+    u32::try_from(data)
+        .ok()
+        .and_then(PtraceOptions::from_bits)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the ptrace options are not supported"))
+}
+
+/// Parses the signal to deliver when the tracee is resumed, where zero means no signal.
+fn resume_signal(data: usize) -> Result<Option<SigNum>> {
+    if data == 0 {
+        return Ok(None);
+    }
+
+    u8::try_from(data)
+        .ok()
+        .and_then(|sig_num| SigNum::try_from(sig_num).ok())
+        .map(Some)
+        .ok_or_else(|| Error::with_message(Errno::EIO, "the signal is invalid"))
+}
+
+/// Accesses the memory of the tracee, where the errors are reported as `EIO` like Linux.
+fn access_tracee_memory(thread: &Thread, op: impl FnOnce(&Vmar<Full>) -> Result<()>) -> Result<()> {
+    let process = thread.as_posix_thread().unwrap().process();
+    let root_vmar = process.lock_root_vmar();
+    root_vmar
+        .get()
+        .ok_or_else(|| Error::with_message(Errno::ESRCH, "the tracee has exited"))
+        .and_then(|vmar| {
+            op(vmar).map_err(|_| {
+                Error::with_message(Errno::EIO, "the memory of the tracee is inaccessible")
+            })
+        })
+}
@@ -36,5 +36,6 @@ pub fn sys_pwrite64(
     let user_space = ctx.user_space();
     let mut reader = user_space.reader(user_buf_ptr, user_buf_len)?;
     let write_len = file.write_at(offset as _, &mut reader)?;
+    ctx.process.io_stats().account_write(write_len);
     Ok(SyscallReturn::Return(write_len as _))
 }
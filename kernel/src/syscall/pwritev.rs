@@ -1,8 +1,14 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use super::SyscallReturn;
+use super::{
+    preadv::{do_with_rwf_flags, RWFFlag},
+    SyscallReturn,
+};
 use crate::{
-    fs::file_table::{get_file_fast, FileDesc},
+    fs::{
+        file_handle::FileLike,
+        file_table::{get_file_fast, FileDesc},
+    },
     prelude::*,
     util::VmReaderArray,
 };
@@ -13,7 +19,8 @@ pub fn sys_writev(
     io_vec_count: usize,
     ctx: &Context,
 ) -> Result<SyscallReturn> {
-    let res = do_sys_writev(fd, io_vec_ptr, io_vec_count, ctx)?;
+    let res = do_sys_writev(fd, io_vec_ptr, io_vec_count, RWFFlag::empty(), ctx)?;
+    ctx.process.io_stats().account_write(res);
     Ok(SyscallReturn::Return(res as _))
 }
 
@@ -25,6 +32,7 @@ pub fn sys_pwritev(
     ctx: &Context,
 ) -> Result<SyscallReturn> {
     let res = do_sys_pwritev(fd, io_vec_ptr, io_vec_count, offset, RWFFlag::empty(), ctx)?;
+    ctx.process.io_stats().account_write(res);
     Ok(SyscallReturn::Return(res as _))
 }
 
@@ -36,15 +44,15 @@ pub fn sys_pwritev2(
     flags: u32,
     ctx: &Context,
 ) -> Result<SyscallReturn> {
-    let flags = match RWFFlag::from_bits(flags) {
-        Some(flags) => flags,
-        None => return_errno_with_message!(Errno::EINVAL, "invalid flags"),
-    };
-    let res = if offset == -1 {
-        do_sys_writev(fd, io_vec_ptr, io_vec_count, ctx)?
-    } else {
-        do_sys_pwritev(fd, io_vec_ptr, io_vec_count, offset, flags, ctx)?
-    };
+    let flags = RWFFlag::from_user(flags)?;
+    let res = do_with_rwf_flags(flags, ctx, || {
+        if offset == -1 {
+            do_sys_writev(fd, io_vec_ptr, io_vec_count, flags, ctx)
+        } else {
+            do_sys_pwritev(fd, io_vec_ptr, io_vec_count, offset, flags, ctx)
+        }
+    })?;
+    ctx.process.io_stats().account_write(res);
     Ok(SyscallReturn::Return(res as _))
 }
 
@@ -53,10 +61,9 @@ fn do_sys_pwritev(
     io_vec_ptr: Vaddr,
     io_vec_count: usize,
     offset: i64,
-    _flags: RWFFlag,
+    flags: RWFFlag,
     ctx: &Context,
 ) -> Result<usize> {
-    // TODO: Implement flags support
     debug!(
         "fd = {}, io_vec_ptr = 0x{:x}, io_vec_counter = 0x{:x}, offset = 0x{:x}",
         fd, io_vec_ptr, io_vec_count, offset
@@ -105,10 +112,17 @@ fn do_sys_pwritev(
         // but the current implementation does not ensure atomicity.
         // A suitable fix would be to add a `writev` method for the `FileLike` trait,
         // allowing each subsystem to implement atomicity.
-        let write_len = file.write_at(cur_offset, reader)?;
+        let write_len = match file.write_at(cur_offset, reader) {
+            Ok(write_len) => write_len,
+            // Report the data that have been written, if any.
+            Err(_) if total_len > 0 => break,
+            Err(err) => return Err(err),
+        };
         total_len += write_len;
         cur_offset += write_len;
     }
+
+    sync_after_write(file.as_ref(), flags)?;
     Ok(total_len)
 }
 
@@ -116,6 +130,7 @@ fn do_sys_writev(
     fd: FileDesc,
     io_vec_ptr: Vaddr,
     io_vec_count: usize,
+    flags: RWFFlag,
     ctx: &Context,
 ) -> Result<usize> {
     debug!(
@@ -141,17 +156,30 @@ fn do_sys_writev(
         // but the current implementation does not ensure atomicity.
         // A suitable fix would be to add a `writev` method for the `FileLike` trait,
         // allowing each subsystem to implement atomicity.
-        let write_len = file.write(reader)?;
+        let write_len = match file.write(reader) {
+            Ok(write_len) => write_len,
+            // Report the data that have been written, if any.
+            Err(_) if total_len > 0 => break,
+            Err(err) => return Err(err),
+        };
         total_len += write_len;
     }
+
+    sync_after_write(file.as_ref(), flags)?;
     Ok(total_len)
 }
 
-bitflags! {
-    struct RWFFlag: u32 {
-        const RWF_DSYNC = 0x00000001;
-        const RWF_HIPRI = 0x00000002;
-        const RWF_SYNC = 0x00000004;
-        const RWF_NOWAIT = 0x00000008;
+/// Flushes the written data to the storage if `RWF_DSYNC` or `RWF_SYNC` is specified.
+fn sync_after_write(file: &dyn FileLike, flags: RWFFlag) -> Result<()> {
+    // Only the files that are backed by inodes have the data to flush.
+    let Ok(inode_handle) = file.as_inode_or_err() else {
+        return Ok(());
+    };
+
+    if flags.contains(RWFFlag::RWF_SYNC) {
+        inode_handle.dentry().sync_all()?;
+    } else if flags.contains(RWFFlag::RWF_DSYNC) {
+        inode_handle.dentry().sync_data()?;
     }
+    Ok(())
 }
@@ -37,5 +37,6 @@ pub fn sys_read(
         _ => err,
     })?;
 
+    ctx.process.io_stats().account_read(read_len);
     Ok(SyscallReturn::Return(read_len as _))
 }
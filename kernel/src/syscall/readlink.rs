@@ -32,6 +32,7 @@ pub fn sys_readlinkat(
         let fs_path = FsPath::new(dirfd, path.as_ref())?;
         ctx.posix_thread
             .fs()
+            .read()
             .resolver()
             .read()
             .lookup_no_follow(&fs_path)?
@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    power::{set_ctrl_alt_del_enabled, shutdown, PowerAction},
+    prelude::*,
+    process::credentials::capabilities::CapSet,
+};
+
+pub fn sys_reboot(
+    magic: u32,
+    magic2: u32,
+    cmd: u32,
+    _arg: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "magic = 0x{:x}, magic2 = 0x{:x}, cmd = 0x{:x}",
+        magic, magic2, cmd
+    );
+
+    if !ctx
+        .posix_thread
+        .credentials()
+        .has_capability(CapSet::SYS_BOOT)
+    {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "the current thread does not have the CAP_SYS_BOOT capability"
+        );
+    }
+
+    if magic != LINUX_REBOOT_MAGIC1 || !LINUX_REBOOT_MAGIC2.contains(&magic2) {
+        return_errno_with_message!(Errno::EINVAL, "the magic numbers are invalid");
+    }
+
+    let cmd = RebootCmd::try_from(cmd)
+        .map_err(|_| Error::with_message(Errno::EINVAL, "the reboot command is not supported"))?;
+    match cmd {
+        // The command string of `LINUX_REBOOT_CMD_RESTART2` is ignored.
+        RebootCmd::Restart | RebootCmd::Restart2 => shutdown(PowerAction::Restart),
+        // There is no ROM monitor to return to, so halting the system is the same as powering
+        // it off.
+        RebootCmd::Halt | RebootCmd::PowerOff => shutdown(PowerAction::PowerOff),
+        RebootCmd::CadOn => set_ctrl_alt_del_enabled(true),
+        RebootCmd::CadOff => set_ctrl_alt_del_enabled(false),
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
+
+const LINUX_REBOOT_MAGIC1: u32 = 0xfee1dead;
+const LINUX_REBOOT_MAGIC2: [u32; 4] = [672274793, 85072278, 369367448, 537993216];
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, TryFromInt)]
+enum RebootCmd {
+    Restart = 0x01234567,
+    Halt = 0xCDEF0123,
+    CadOn = 0x89ABCDEF,
+    CadOff = 0x00000000,
+    PowerOff = 0x4321FEDC,
+    Restart2 = 0xA1B2C3D4,
+}
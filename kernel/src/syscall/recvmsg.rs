@@ -14,7 +14,7 @@ pub fn sys_recvmsg(
     flags: i32,
     ctx: &Context,
 ) -> Result<SyscallReturn> {
-    let c_user_msghdr: CUserMsgHdr = ctx.user_space().read_val(user_msghdr_ptr)?;
+    let mut c_user_msghdr: CUserMsgHdr = ctx.user_space().read_val(user_msghdr_ptr)?;
     let flags = SendRecvFlags::from_bits_truncate(flags);
 
     debug!(
@@ -22,11 +22,11 @@ pub fn sys_recvmsg(
         sockfd, c_user_msghdr, flags
     );
 
-    let mut file_table = ctx.thread_local.borrow_file_table_mut();
-    let file = get_file_fast!(&mut file_table, sockfd);
-    let socket = file.as_socket_or_err()?;
+    let (total_bytes, mut message_header) = {
+        let mut file_table = ctx.thread_local.borrow_file_table_mut();
+        let file = get_file_fast!(&mut file_table, sockfd);
+        let socket = file.as_socket_or_err()?;
 
-    let (total_bytes, message_header) = {
         let user_space = ctx.user_space();
         let mut io_vec_writer = c_user_msghdr.copy_writer_array_from_user(&user_space)?;
         socket
@@ -38,13 +38,18 @@ pub fn sys_recvmsg(
             })?
     };
 
-    if let Some(addr) = message_header.addr() {
-        c_user_msghdr.write_socket_addr_to_user(addr)?;
-    }
+    c_user_msghdr.write_socket_addr_to_user(message_header.addr())?;
 
-    if c_user_msghdr.msg_control != 0 {
-        warn!("receiving control message is not supported");
-    }
+    // The file table is no longer borrowed here, so the received files can be installed.
+    c_user_msghdr.msg_flags = message_header.flags().bits() as u32;
+    c_user_msghdr.write_control_messages_to_user(
+        message_header.take_control_messages(),
+        flags.contains(SendRecvFlags::MSG_CMSG_CLOEXEC),
+        ctx,
+    )?;
+
+    ctx.user_space()
+        .write_val(user_msghdr_ptr, &c_user_msghdr)?;
 
     Ok(SyscallReturn::Return(total_bytes as _))
 }
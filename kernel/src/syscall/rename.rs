@@ -8,9 +8,16 @@ use crate::{
         utils::InodeType,
     },
     prelude::*,
+    sched::PiMutex,
     syscall::constants::MAX_FILENAME_LEN,
 };
 
+/// The lock that serializes the renames.
+///
+/// Otherwise, two racing renames can both pass the path prefix check below and move a
+/// directory into its own subdirectory. This is like `s_vfs_rename_mutex` in Linux.
+static RENAME_LOCK: PiMutex<()> = PiMutex::new(());
+
 pub fn sys_renameat(
     old_dirfd: FileDesc,
     old_path_addr: Vaddr,
@@ -26,7 +33,8 @@ pub fn sys_renameat(
         old_dirfd, old_path, new_dirfd, new_path
     );
 
-    let fs = ctx.posix_thread.fs().resolver().read();
+    let fs_ref = ctx.posix_thread.fs().read();
+    let fs = fs_ref.resolver().read();
 
     let (old_dir_dentry, old_name) = {
         let old_path = old_path.to_string_lossy();
@@ -50,6 +58,8 @@ pub fn sys_renameat(
         fs.lookup_dir_and_base_name(&new_fs_path)?
     };
 
+    let _rename_guard = RENAME_LOCK.lock();
+
     // Check abs_path
     let old_abs_path = old_dentry.abs_path();
     let new_abs_path = new_dir_dentry.abs_path() + "/" + &new_name;
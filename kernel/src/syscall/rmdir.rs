@@ -30,6 +30,7 @@ pub(super) fn sys_rmdirat(
         let fs_path = FsPath::new(dirfd, path_addr.as_ref())?;
         ctx.posix_thread
             .fs()
+            .read()
             .resolver()
             .read()
             .lookup_dir_and_base_name(&fs_path)?
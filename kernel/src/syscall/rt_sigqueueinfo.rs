@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    prelude::*,
+    process::{
+        kill,
+        signal::{
+            c_types::siginfo_t,
+            constants::SI_TKILL,
+            sig_num::SigNum,
+            signals::user::{UserSignal, UserSignalKind},
+        },
+        tgkill, Pid,
+    },
+    thread::Tid,
+};
+
+pub fn sys_rt_sigqueueinfo(
+    pid: Pid,
+    sig_num: u8,
+    siginfo_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "pid = {}, sig_num = {}, siginfo_addr = 0x{:x}",
+        pid, sig_num, siginfo_addr
+    );
+
+    let signal = read_queued_signal(pid, sig_num, siginfo_addr, ctx)?;
+    kill(pid, signal, ctx)?;
+    Ok(SyscallReturn::Return(0))
+}
+
+pub fn sys_rt_tgsigqueueinfo(
+    tgid: Pid,
+    tid: Tid,
+    sig_num: u8,
+    siginfo_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "tgid = {}, tid = {}, sig_num = {}, siginfo_addr = 0x{:x}",
+        tgid, tid, sig_num, siginfo_addr
+    );
+
+    if (tgid as i32) <= 0 || (tid as i32) <= 0 {
+        return_errno_with_message!(Errno::EINVAL, "the thread group ID or thread ID is invalid");
+    }
+
+    let signal = read_queued_signal(tid, sig_num, siginfo_addr, ctx)?;
+    tgkill(tid, tgid, signal, ctx)?;
+    Ok(SyscallReturn::Return(0))
+}
+
+/// Reads the `siginfo_t` provided by the user and builds the signal to send to `target_id`.
+///
+/// If `sig_num` is zero, no signal is built, but the permission is still checked.
+fn read_queued_signal(
+    target_id: u32,
+    sig_num: u8,
+    siginfo_addr: Vaddr,
+    ctx: &Context,
+) -> Result<Option<UserSignal>> {
+    let info = ctx.user_space().read_val::<siginfo_t>(siginfo_addr)?;
+
+    // A process cannot impersonate the kernel or `kill`/`tgkill` when sending signals to others,
+    // as these codes indicate the sender by the PID and UID fields, which are not verified.
+    if (info.si_code >= 0 || info.si_code == SI_TKILL) && target_id != ctx.posix_thread.tid() {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "the signal code cannot be used to send signals to others"
+        );
+    }
+
+    if sig_num == 0 {
+        return Ok(None);
+    }
+
+    let sig_num = SigNum::try_from(sig_num)?;
+    let pid = ctx.process.pid();
+    let uid = ctx.posix_thread.credentials().ruid();
+    Ok(Some(UserSignal::new(
+        sig_num,
+        UserSignalKind::Sigqueue(info),
+        pid,
+        uid,
+    )))
+}
@@ -5,7 +5,15 @@ use core::sync::atomic::Ordering;
 use ostd::{cpu::context::UserContext, user::UserContextApi};
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::signal::c_types::ucontext_t};
+use crate::{
+    arch::signal::restore_fpu_state_from_user,
+    prelude::*,
+    process::signal::{
+        c_types::ucontext_t,
+        constants::{SIGKILL, SIGSTOP},
+        sig_mask::SigMask,
+    },
+};
 
 pub fn sys_rt_sigreturn(ctx: &Context, user_ctx: &mut UserContext) -> Result<SyscallReturn> {
     let Context {
@@ -41,18 +49,18 @@ pub fn sys_rt_sigreturn(ctx: &Context, user_ctx: &mut UserContext) -> Result<Sys
     } else {
         thread_local.sig_context().set(Some(ucontext.uc_link));
     };
-    ucontext
-        .uc_mcontext
-        .inner
-        .gp_regs
-        .copy_to_raw(user_ctx.general_regs_mut());
-
-    // unblock sig mask
-    let sig_mask = ucontext.uc_sigmask;
-    let old_mask = posix_thread.sig_mask().load(Ordering::Relaxed);
-    posix_thread
-        .sig_mask()
-        .store(old_mask - sig_mask, Ordering::Relaxed);
+    ucontext.uc_mcontext.copy_to_context(user_ctx);
+    // The signal handler may have changed the FPU state in the signal frame, e.g., to resume
+    // from a different point, so the state must be restored from the frame.
+    restore_fpu_state_from_user(user_ctx, &ucontext)?;
+
+    // Restore the signal mask before the signal handler is invoked, or the one that the handler
+    // has written to the frame.
+    let mut sig_mask = SigMask::from(ucontext.uc_sigmask);
+    // It is not possible to block SIGKILL or SIGSTOP.
+    sig_mask -= SIGKILL;
+    sig_mask -= SIGSTOP;
+    posix_thread.sig_mask().store(sig_mask, Ordering::Relaxed);
 
     Ok(SyscallReturn::NoReturn)
 }
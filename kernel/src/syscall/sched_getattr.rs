@@ -128,30 +128,15 @@ pub(super) fn read_linux_sched_attr_from_user(
     addr: Vaddr,
     ctx: &Context,
 ) -> Result<LinuxSchedAttr> {
-    let type_size = mem::size_of::<LinuxSchedAttr>();
-
     let space = ctx.user_space();
 
-    let mut attr = LinuxSchedAttr::default();
-
-    space.read_bytes(
-        addr,
-        &mut VmWriter::from(&mut attr.as_bytes_mut()[..mem::size_of::<u32>()]),
-    )?;
-
-    let size = type_size.min(attr.size as usize);
-    space.read_bytes(addr, &mut VmWriter::from(&mut attr.as_bytes_mut()[..size]))?;
-
-    if let Some(additional_size) = attr.size.checked_sub(type_size as u32) {
-        let mut buf = vec![0; additional_size as usize];
-        space.read_bytes(addr + type_size, &mut VmWriter::from(&mut *buf))?;
-
-        if buf.iter().any(|&b| b != 0) {
-            return Err(Error::with_message(Errno::E2BIG, "too big sched_attr"));
-        }
+    // The first field is the size of the structure.
+    let size = space.read_val::<u32>(addr)?;
+    if size as usize > PAGE_SIZE {
+        return_errno_with_message!(Errno::E2BIG, "the size of sched_attr is too large");
     }
 
-    Ok(attr)
+    space.read_struct(addr, size as usize)
 }
 
 pub(super) fn write_linux_sched_attr_to_user(
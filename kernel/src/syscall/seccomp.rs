@@ -0,0 +1,331 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use ostd::{cpu::context::UserContext, user::UserContextApi};
+
+use super::{SyscallArgument, SyscallReturn};
+use crate::{
+    fs::file_table::FdFlags,
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        posix_thread::{do_exit, do_exit_group, AsPosixThread},
+        seccomp::{
+            CSeccompNotifSizes, SeccompAction, SeccompData, SeccompMode, SeccompNotifier,
+            SeccompProgram, SockFilter, UserNotifResponse,
+        },
+        signal::{
+            constants::{SIGKILL, SIGSYS},
+            signals::seccomp::SeccompSignal,
+        },
+        ResourceType, TermStatus,
+    },
+};
+
+pub fn sys_seccomp(op: u32, flags: u32, args: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    debug!("op = {}, flags = {:#x}, args = {:#x}", op, flags, args);
+
+    match op {
+        SECCOMP_SET_MODE_STRICT => {
+            if flags != 0 || args != 0 {
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "the strict mode does not accept flags or arguments"
+                );
+            }
+            ctx.posix_thread.seccomp().set_mode_strict()?;
+            Ok(SyscallReturn::Return(0))
+        }
+        SECCOMP_SET_MODE_FILTER => set_mode_filter(flags, args, ctx),
+        SECCOMP_GET_ACTION_AVAIL => {
+            if flags != 0 {
+                return_errno_with_message!(Errno::EINVAL, "the flags are not zero");
+            }
+            let action = ctx.user_space().read_val::<u32>(args)?;
+            if !SeccompAction::is_available(action) {
+                return_errno_with_message!(Errno::EOPNOTSUPP, "the action is not supported");
+            }
+            Ok(SyscallReturn::Return(0))
+        }
+        SECCOMP_GET_NOTIF_SIZES => {
+            if flags != 0 {
+                return_errno_with_message!(Errno::EINVAL, "the flags are not zero");
+            }
+            ctx.user_space()
+                .write_val(args, &CSeccompNotifSizes::new())?;
+            Ok(SyscallReturn::Return(0))
+        }
+        _ => return_errno_with_message!(Errno::EINVAL, "the seccomp operation is not supported"),
+    }
+}
+
+/// Installs a seccomp filter for the current thread (or all the threads of the current process,
+/// with [`SeccompFilterFlags::TSYNC`]).
+///
+/// With [`SeccompFilterFlags::NEW_LISTENER`], the file descriptor of the listener file is
+/// returned.
+///
+/// This is the common part of `seccomp(SECCOMP_SET_MODE_FILTER)` and
+/// `prctl(PR_SET_SECCOMP, SECCOMP_MODE_FILTER)`.
+pub(super) fn set_mode_filter(
+    flags: u32,
+    fprog_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let Some(flags) = SeccompFilterFlags::from_bits(flags) else {
+        return_errno_with_message!(Errno::EINVAL, "the seccomp filter flags are invalid");
+    };
+    if flags.contains(SeccompFilterFlags::WAIT_KILLABLE_RECV)
+        && !flags.contains(SeccompFilterFlags::NEW_LISTENER)
+    {
+        return_errno_with_message!(Errno::EINVAL, "the killable wait requires a listener");
+    }
+    // The return value cannot be both a file descriptor and a TID.
+    if flags.contains(SeccompFilterFlags::NEW_LISTENER | SeccompFilterFlags::TSYNC)
+        && !flags.contains(SeccompFilterFlags::TSYNC_ESRCH)
+    {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "a listener cannot be created with `TSYNC` unless `TSYNC_ESRCH` is set"
+        );
+    }
+
+    // A thread without privileges must not be able to affect the privileged programs that it
+    // executes (e.g., by failing the system calls that drop the privileges).
+    if !ctx.posix_thread.no_new_privs()
+        && !ctx
+            .posix_thread
+            .credentials()
+            .has_capability(CapSet::SYS_ADMIN)
+    {
+        return_errno_with_message!(
+            Errno::EACCES,
+            "installing seccomp filters requires `no_new_privs` or `CAP_SYS_ADMIN`"
+        );
+    }
+
+    let program = read_program_from_user(fprog_addr, ctx)?;
+
+    if !flags.contains(SeccompFilterFlags::NEW_LISTENER) {
+        return add_filter(flags, program, None, ctx);
+    }
+
+    // The listener file is installed first, so that the filter is not installed if there are no
+    // file descriptors available.
+    let (notifier, listener) =
+        SeccompNotifier::new_with_listener(flags.contains(SeccompFilterFlags::WAIT_KILLABLE_RECV));
+    let file_table = ctx.thread_local.borrow_file_table();
+    let listener_fd = file_table
+        .unwrap()
+        .write()
+        .insert(listener, FdFlags::CLOEXEC)?;
+
+    if let Err(err) = add_filter(flags, program, Some(notifier), ctx) {
+        let listener = file_table.unwrap().write().close_file(listener_fd);
+        drop(listener);
+        return Err(err);
+    }
+
+    Ok(SyscallReturn::Return(listener_fd as _))
+}
+
+fn add_filter(
+    flags: SeccompFilterFlags,
+    program: SeccompProgram,
+    notifier: Option<Arc<SeccompNotifier>>,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let seccomp = ctx.posix_thread.seccomp();
+
+    if !flags.contains(SeccompFilterFlags::TSYNC) {
+        seccomp.add_filter(program, notifier)?;
+        return Ok(SyscallReturn::Return(0));
+    }
+
+    let tasks = ctx.process.tasks().lock();
+    let other_threads = tasks
+        .as_slice()
+        .iter()
+        .map(|task| task.as_posix_thread().unwrap())
+        .filter(|posix_thread| !core::ptr::eq(*posix_thread, ctx.posix_thread))
+        .collect::<Vec<_>>();
+
+    if let Err(tid) = seccomp.add_filter_synced(program, notifier, &other_threads)? {
+        if flags.contains(SeccompFilterFlags::TSYNC_ESRCH) {
+            return_errno_with_message!(Errno::ESRCH, "a thread cannot be synchronized");
+        }
+        return Ok(SyscallReturn::Return(tid as _));
+    }
+
+    if ctx.posix_thread.no_new_privs() {
+        other_threads
+            .iter()
+            .for_each(|thread| thread.set_no_new_privs());
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
+
+fn read_program_from_user(fprog_addr: Vaddr, ctx: &Context) -> Result<SeccompProgram> {
+    let user_space = ctx.user_space();
+
+    let fprog = user_space.read_val::<CSockFprog>(fprog_addr)?;
+    let len = fprog.len as usize;
+    if len == 0 || len > SeccompProgram::MAX_LEN {
+        return_errno_with_message!(Errno::EINVAL, "the BPF program length is invalid");
+    }
+
+    let mut filters = Vec::with_capacity(len);
+    for i in 0..len {
+        let filter_addr = fprog.filter + i * size_of::<SockFilter>();
+        filters.push(user_space.read_val::<SockFilter>(filter_addr)?);
+    }
+
+    SeccompProgram::new(&filters)
+}
+
+/// Checks the system call against the seccomp mode of the current thread.
+///
+/// If the system call is allowed, this function returns `None`. Otherwise, the system call
+/// should be skipped, and this function returns its return value.
+pub(super) fn check_syscall(
+    syscall: &SyscallArgument,
+    ctx: &Context,
+    user_ctx: &UserContext,
+) -> Option<SyscallReturn> {
+    let seccomp = ctx.posix_thread.seccomp();
+
+    let action = match seccomp.mode() {
+        SeccompMode::Disabled => return None,
+        SeccompMode::Strict => {
+            if STRICT_MODE_SYSCALLS.contains(&syscall.syscall_number) {
+                return None;
+            }
+            // Only the thread is killed, as in Linux.
+            do_exit(TermStatus::Killed(SIGKILL));
+            return Some(SyscallReturn::NoReturn);
+        }
+        SeccompMode::Filter => seccomp.run_filters(&seccomp_data(syscall, user_ctx)),
+    };
+
+    match action {
+        SeccompAction::Allow => None,
+        SeccompAction::Log => {
+            info!(
+                "[seccomp] the system call {} is allowed and logged",
+                syscall.syscall_number
+            );
+            None
+        }
+        SeccompAction::Errno(errno) => {
+            let errno = errno.min(MAX_ERRNO);
+            Some(SyscallReturn::Return(-(errno as isize)))
+        }
+        SeccompAction::Trap(errno) => {
+            let signal = SeccompSignal::new(
+                user_ctx.instruction_pointer(),
+                syscall.syscall_number as i32,
+                AUDIT_ARCH,
+                errno,
+            );
+            ctx.posix_thread.enqueue_signal(Box::new(signal));
+            // The signal handler sees the system call number as the return value, as in Linux.
+            Some(SyscallReturn::Return(syscall.syscall_number as isize))
+        }
+        SeccompAction::UserNotif(Some(notifier)) => {
+            let file_table = ctx.thread_local.borrow_file_table().unwrap().clone();
+            let nofile_limit = ctx
+                .process
+                .resource_limits()
+                .get_rlimit(ResourceType::RLIMIT_NOFILE)
+                .get_cur();
+            let res = notifier.notify(
+                ctx.posix_thread.tid(),
+                seccomp_data(syscall, user_ctx),
+                file_table,
+                nofile_limit.try_into().unwrap_or(usize::MAX),
+            );
+            match res {
+                Ok(UserNotifResponse::Continue) => None,
+                Ok(UserNotifResponse::Return(value)) => Some(SyscallReturn::Return(value)),
+                Err(err) => Some(SyscallReturn::Return(-(err.error() as isize))),
+            }
+        }
+        // There is never a tracer to notify. The filter that returns `SECCOMP_RET_USER_NOTIF`
+        // may also have no listener.
+        SeccompAction::Trace | SeccompAction::UserNotif(None) => {
+            Some(SyscallReturn::Return(-(Errno::ENOSYS as isize)))
+        }
+        SeccompAction::KillThread => {
+            do_exit(TermStatus::Killed(SIGSYS));
+            Some(SyscallReturn::NoReturn)
+        }
+        SeccompAction::KillProcess => {
+            do_exit_group(TermStatus::Killed(SIGSYS));
+            Some(SyscallReturn::NoReturn)
+        }
+    }
+}
+
+fn seccomp_data(syscall: &SyscallArgument, user_ctx: &UserContext) -> SeccompData {
+    SeccompData {
+        nr: syscall.syscall_number as i32,
+        arch: AUDIT_ARCH,
+        instruction_pointer: user_ctx.instruction_pointer() as u64,
+        args: syscall.args,
+    }
+}
+
+/// The system calls that are allowed in the strict mode.
+#[cfg(target_arch = "x86_64")]
+const STRICT_MODE_SYSCALLS: [u64; 4] = {
+    use super::arch::{SYS_EXIT, SYS_READ, SYS_RT_SIGRETURN, SYS_WRITE};
+    [SYS_READ, SYS_WRITE, SYS_EXIT, SYS_RT_SIGRETURN]
+};
+/// The system calls that are allowed in the strict mode.
+// TODO: Add `rt_sigreturn` after it is supported on RISC-V.
+#[cfg(target_arch = "riscv64")]
+const STRICT_MODE_SYSCALLS: [u64; 3] = {
+    use super::arch::{SYS_EXIT, SYS_READ, SYS_WRITE};
+    [SYS_READ, SYS_WRITE, SYS_EXIT]
+};
+
+/// The `AUDIT_ARCH_*` value of the system call convention.
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: u32 = 0xc000_003e;
+/// The `AUDIT_ARCH_*` value of the system call convention.
+#[cfg(target_arch = "riscv64")]
+const AUDIT_ARCH: u32 = 0xc000_00f3;
+
+/// The maximum error number that a filter can return.
+const MAX_ERRNO: u16 = 4095;
+
+const SECCOMP_SET_MODE_STRICT: u32 = 0;
+const SECCOMP_SET_MODE_FILTER: u32 = 1;
+const SECCOMP_GET_ACTION_AVAIL: u32 = 2;
+const SECCOMP_GET_NOTIF_SIZES: u32 = 3;
+
+bitflags! {
+    struct SeccompFilterFlags: u32 {
+        const TSYNC = 1 << 0;
+        /// Logs the actions except `SECCOMP_RET_ALLOW`. Currently, only `SECCOMP_RET_LOG` is
+        /// logged, so this flag has no effect.
+        const LOG = 1 << 1;
+        /// Disables the speculative store bypass mitigation, which has no effect.
+        const SPEC_ALLOW = 1 << 2;
+        /// Creates a listener file, from which a supervisor receives the system calls for which
+        /// the filter returns `SECCOMP_RET_USER_NOTIF`.
+        const NEW_LISTENER = 1 << 3;
+        const TSYNC_ESRCH = 1 << 4;
+        /// Waits for the response killably once the notification is received by the supervisor.
+        const WAIT_KILLABLE_RECV = 1 << 5;
+    }
+}
+
+/// A classic BPF program in the user space (`struct sock_fprog` in Linux).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CSockFprog {
+    len: u16,
+    _padding: [u8; 6],
+    filter: Vaddr,
+}
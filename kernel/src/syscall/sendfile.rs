@@ -117,5 +117,10 @@ pub fn sys_sendfile(
         ctx.user_space().write_val(offset_ptr, &(offset as isize))?;
     }
 
+    // Like Linux, the system call is accounted as both a read and a write.
+    let io_stats = ctx.process.io_stats();
+    io_stats.account_read(total_len);
+    io_stats.account_write(total_len);
+
     Ok(SyscallReturn::Return(total_len as _))
 }
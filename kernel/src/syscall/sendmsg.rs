@@ -23,23 +23,18 @@ pub fn sys_sendmsg(
     );
 
     let mut file_table = ctx.thread_local.borrow_file_table_mut();
-    let file = get_file_fast!(&mut file_table, sockfd);
+    let file = get_file_fast!(&mut file_table, sockfd).into_owned();
+    // Drop `file_table` as the files passed in the control messages need to be looked up.
+    drop(file_table);
     let socket = file.as_socket_or_err()?;
 
     let user_space = ctx.user_space();
     let (mut io_vec_reader, message_header) = {
         let addr = c_user_msghdr.read_socket_addr_from_user()?;
         let io_vec_reader = c_user_msghdr.copy_reader_array_from_user(&user_space)?;
+        let control_messages = c_user_msghdr.read_control_messages_from_user(ctx)?;
 
-        let control_message = {
-            if c_user_msghdr.msg_control != 0 {
-                // TODO: support sending control message
-                warn!("control message is not supported now");
-            }
-            None
-        };
-
-        (io_vec_reader, MessageHeader::new(addr, control_message))
+        (io_vec_reader, MessageHeader::new(addr, control_messages))
     };
 
     let total_bytes = socket
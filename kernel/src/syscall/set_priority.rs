@@ -5,7 +5,9 @@ use core::sync::atomic::Ordering;
 use super::SyscallReturn;
 use crate::{
     prelude::*,
-    process::ResourceType::RLIMIT_NICE,
+    process::{
+        credentials::capabilities::CapSet, posix_thread::AsPosixThread, ResourceType::RLIMIT_NICE,
+    },
     sched::Nice,
     syscall::get_priority::{get_processes, PriorityTarget},
 };
@@ -25,14 +27,34 @@ pub fn sys_set_priority(which: i32, who: u32, prio: i32, ctx: &Context) -> Resul
         prio_target, new_nice
     );
 
+    let credentials = ctx.posix_thread.credentials();
+    let has_nice_capability = credentials.has_capability(CapSet::SYS_NICE);
+
     let processes = get_processes(prio_target)?;
     for process in processes.iter() {
+        // Only the processes of the same user can be reniced without the `CAP_SYS_NICE`
+        // capability.
+        let (target_ruid, target_euid) = {
+            let main_thread = process.main_thread();
+            let target_credentials = main_thread.as_posix_thread().unwrap().credentials();
+            (target_credentials.ruid(), target_credentials.euid())
+        };
+        if !has_nice_capability
+            && target_ruid != credentials.euid()
+            && target_euid != credentials.euid()
+        {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "renicing the processes of other users requires the CAP_SYS_NICE capability"
+            );
+        }
+
         let rlimit = process.resource_limits();
         let limit = (rlimit.get_rlimit(RLIMIT_NICE).get_cur() as i8)
             .try_into()
             .map_err(|msg| Error::with_message(Errno::EINVAL, msg))?;
 
-        if new_nice < limit {
+        if new_nice < limit && !has_nice_capability {
             return_errno!(Errno::EACCES);
         }
         process.nice().store(new_nice, Ordering::Relaxed);
@@ -20,10 +20,9 @@ pub fn sys_set_robust_list(
         );
     }
 
-    let robust_list_head: RobustListHead = ctx.user_space().read_val(robust_list_head_ptr)?;
-    debug!("robust list head: {:x?}", robust_list_head);
-
-    *ctx.thread_local.robust_list().borrow_mut() = Some(robust_list_head);
+    // The robust list head is not read until the thread exits, as Linux does. The user space
+    // keeps updating it while locking and unlocking robust futexes.
+    *ctx.thread_local.robust_list().borrow_mut() = Some(robust_list_head_ptr);
 
     Ok(SyscallReturn::Return(0))
 }
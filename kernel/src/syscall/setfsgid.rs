@@ -1,15 +1,18 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::Gid};
+use crate::prelude::*;
 
 pub fn sys_setfsgid(gid: i32, ctx: &Context) -> Result<SyscallReturn> {
     debug!("gid = {}", gid);
 
+    let user_ns = ctx.posix_thread.credentials().user_ns();
+
+    // Like Linux, an ID that is not mapped in the user namespace is not set.
     let fsgid = if gid < 0 {
         None
     } else {
-        Some(Gid::new(gid as u32))
+        user_ns.gid_to_kernel(gid as u32).ok()
     };
 
     let old_fsgid = {
@@ -18,6 +21,6 @@ pub fn sys_setfsgid(gid: i32, ctx: &Context) -> Result<SyscallReturn> {
     };
 
     Ok(SyscallReturn::Return(
-        <Gid as Into<u32>>::into(old_fsgid) as _
+        user_ns.gid_from_kernel_or_overflow(old_fsgid) as _,
     ))
 }
@@ -1,15 +1,18 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::Uid};
+use crate::prelude::*;
 
 pub fn sys_setfsuid(uid: i32, ctx: &Context) -> Result<SyscallReturn> {
     debug!("uid = {}", uid);
 
+    let user_ns = ctx.posix_thread.credentials().user_ns();
+
+    // Like Linux, an ID that is not mapped in the user namespace is not set.
     let fsuid = if uid < 0 {
         None
     } else {
-        Some(Uid::new(uid as u32))
+        user_ns.uid_to_kernel(uid as u32).ok()
     };
 
     let old_fsuid = {
@@ -18,6 +21,6 @@ pub fn sys_setfsuid(uid: i32, ctx: &Context) -> Result<SyscallReturn> {
     };
 
     Ok(SyscallReturn::Return(
-        <Uid as Into<u32>>::into(old_fsuid) as _
+        user_ns.uid_from_kernel_or_overflow(old_fsuid) as _,
     ))
 }
@@ -1,7 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::Gid};
+use crate::prelude::*;
 
 pub fn sys_setgid(gid: i32, ctx: &Context) -> Result<SyscallReturn> {
     debug!("gid = {}", gid);
@@ -10,10 +10,14 @@ pub fn sys_setgid(gid: i32, ctx: &Context) -> Result<SyscallReturn> {
         return_errno_with_message!(Errno::EINVAL, "gid cannot be negative");
     }
 
-    let gid = Gid::new(gid as u32);
+    let gid = ctx
+        .posix_thread
+        .credentials()
+        .user_ns()
+        .gid_to_kernel(gid as u32)?;
 
     let credentials = ctx.posix_thread.credentials_mut();
-    credentials.set_gid(gid);
+    credentials.set_gid(gid)?;
 
     Ok(SyscallReturn::Return(0))
 }
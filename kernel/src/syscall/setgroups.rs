@@ -1,12 +1,25 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::Gid};
+use crate::{prelude::*, process::credentials::capabilities::CapSet};
 
 pub fn sys_setgroups(size: usize, group_list_addr: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
     debug!("size = {}, group_list_addr = 0x{:x}", size, group_list_addr);
 
-    // TODO: check perm: the calling process should have the CAP_SETGID capability
+    let credentials = ctx.posix_thread.credentials();
+    let user_ns = credentials.user_ns();
+    if !credentials.has_capability_in(CapSet::SETGID, &user_ns) {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "the current thread does not have the CAP_SETGID capability"
+        );
+    }
+    if !user_ns.may_setgroups() {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "setgroups is not allowed in the user namespace"
+        );
+    }
 
     if size > NGROUPS_MAX {
         return_errno_with_message!(Errno::EINVAL, "size cannot be greater than NGROUPS_MAX");
@@ -14,8 +27,8 @@ pub fn sys_setgroups(size: usize, group_list_addr: Vaddr, ctx: &Context) -> Resu
 
     let mut new_groups = BTreeSet::new();
     for idx in 0..size {
-        let addr = group_list_addr + idx * core::mem::size_of::<Gid>();
-        let gid = ctx.user_space().read_val(addr)?;
+        let addr = group_list_addr + idx * core::mem::size_of::<u32>();
+        let gid = user_ns.gid_to_kernel(ctx.user_space().read_val(addr)?)?;
         new_groups.insert(gid);
     }
 
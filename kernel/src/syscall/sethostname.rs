@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        namespace::uts_ns::{UtsNamespace, UTS_NAME_MAX_LEN},
+    },
+};
+
+pub fn sys_sethostname(addr: Vaddr, len: usize, ctx: &Context) -> Result<SyscallReturn> {
+    let name = read_name_from_user(addr, len, ctx)?;
+    debug!("hostname = {:?}", String::from_utf8_lossy(&name));
+
+    UtsNamespace::get_init_singleton().set_hostname(&name)?;
+
+    Ok(SyscallReturn::Return(0))
+}
+
+pub fn sys_setdomainname(addr: Vaddr, len: usize, ctx: &Context) -> Result<SyscallReturn> {
+    let name = read_name_from_user(addr, len, ctx)?;
+    debug!("domainname = {:?}", String::from_utf8_lossy(&name));
+
+    UtsNamespace::get_init_singleton().set_domainname(&name)?;
+
+    Ok(SyscallReturn::Return(0))
+}
+
+fn read_name_from_user(addr: Vaddr, len: usize, ctx: &Context) -> Result<Vec<u8>> {
+    if !ctx
+        .posix_thread
+        .credentials()
+        .has_capability(CapSet::SYS_ADMIN)
+    {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "the current thread does not have the CAP_SYS_ADMIN capability"
+        );
+    }
+
+    // Note that `len` is signed in Linux, so a negative length also fails here.
+    if len > UTS_NAME_MAX_LEN {
+        return_errno_with_message!(Errno::EINVAL, "the name is too long");
+    }
+
+    let mut name = vec![0u8; len];
+    ctx.user_space()
+        .read_bytes(addr, &mut VmWriter::from(name.as_mut_slice()))?;
+
+    Ok(name)
+}
@@ -1,23 +1,25 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::Gid};
+use crate::prelude::*;
 
 pub fn sys_setresgid(rgid: i32, egid: i32, sgid: i32, ctx: &Context) -> Result<SyscallReturn> {
+    let user_ns = ctx.posix_thread.credentials().user_ns();
+
     let rgid = if rgid > 0 {
-        Some(Gid::new(rgid as u32))
+        Some(user_ns.gid_to_kernel(rgid as u32)?)
     } else {
         None
     };
 
     let egid = if egid > 0 {
-        Some(Gid::new(egid as u32))
+        Some(user_ns.gid_to_kernel(egid as u32)?)
     } else {
         None
     };
 
     let sgid = if sgid > 0 {
-        Some(Gid::new(sgid as u32))
+        Some(user_ns.gid_to_kernel(sgid as u32)?)
     } else {
         None
     };
@@ -1,19 +1,21 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::Uid};
+use crate::prelude::*;
 
 pub fn sys_setreuid(ruid: i32, euid: i32, ctx: &Context) -> Result<SyscallReturn> {
     debug!("ruid = {}, euid = {}", ruid, euid);
 
+    let user_ns = ctx.posix_thread.credentials().user_ns();
+
     let ruid = if ruid > 0 {
-        Some(Uid::new(ruid as u32))
+        Some(user_ns.uid_to_kernel(ruid as u32)?)
     } else {
         None
     };
 
     let euid = if euid > 0 {
-        Some(Uid::new(euid as u32))
+        Some(user_ns.uid_to_kernel(euid as u32)?)
     } else {
         None
     };
@@ -0,0 +1,37 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use super::{clock_settime::check_sys_time_capability, SyscallReturn};
+use crate::{
+    prelude::*,
+    time::{set_real_time, timeval_t},
+};
+
+// The use of the timezone structure is obsolete.
+// The kernel only records it for the FAT file systems and the RTC, so just ignore it.
+pub fn sys_settimeofday(
+    timeval_addr: Vaddr,
+    timezone_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "timeval_addr = 0x{:x}, timezone_addr = 0x{:x}",
+        timeval_addr, timezone_addr
+    );
+
+    let real_time = if timeval_addr != 0 {
+        let timeval = ctx.user_space().read_val::<timeval_t>(timeval_addr)?;
+        Some(Duration::try_from(timeval)?)
+    } else {
+        None
+    };
+
+    check_sys_time_capability(ctx)?;
+
+    if let Some(real_time) = real_time {
+        set_real_time(real_time)?;
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
@@ -1,7 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::Uid};
+use crate::prelude::*;
 
 pub fn sys_setuid(uid: i32, ctx: &Context) -> Result<SyscallReturn> {
     debug!("uid = {}", uid);
@@ -10,10 +10,14 @@ pub fn sys_setuid(uid: i32, ctx: &Context) -> Result<SyscallReturn> {
         return_errno_with_message!(Errno::EINVAL, "uid cannot be negative");
     }
 
-    let uid = Uid::new(uid as u32);
+    let uid = ctx
+        .posix_thread
+        .credentials()
+        .user_ns()
+        .uid_to_kernel(uid as u32)?;
 
     let credentials = ctx.posix_thread.credentials_mut();
-    credentials.set_uid(uid);
+    credentials.set_uid(uid)?;
 
     Ok(SyscallReturn::Return(0))
 }
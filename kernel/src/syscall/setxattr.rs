@@ -132,7 +132,8 @@ pub(super) fn lookup_dentry_for_xattr<'a>(
         |path: &CString, ctx: &Context, symlink_no_follow: bool| -> Result<Cow<'_, Dentry>> {
             let path = path.to_string_lossy();
             let fs_path = FsPath::new(AT_FDCWD, path.as_ref())?;
-            let fs = ctx.posix_thread.fs().resolver().read();
+            let fs_ref = ctx.posix_thread.fs().read();
+            let fs = fs_ref.resolver().read();
             let dentry = if symlink_no_follow {
                 fs.lookup_no_follow(&fs_path)?
             } else {
@@ -179,12 +180,11 @@ pub(super) fn parse_xattr_name(name_str: &str) -> Result<XattrName> {
 pub(super) fn check_xattr_namespace(namespace: XattrNamespace, ctx: &Context) -> Result<()> {
     let credentials = ctx.posix_thread.credentials();
     let permitted_capset = credentials.permitted_capset();
-    let effective_capset = credentials.effective_capset();
 
     match namespace {
         XattrNamespace::Trusted => {
             if !permitted_capset.contains(CapSet::SYS_ADMIN)
-                || !effective_capset.contains(CapSet::SYS_ADMIN)
+                || !credentials.has_capability(CapSet::SYS_ADMIN)
             {
                 return_errno_with_message!(
                     Errno::EPERM,
@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use align_ext::AlignExt;
+
+use super::SyscallReturn;
+use crate::{
+    ipc::shm::{get_shm, ShmAtFlags},
+    prelude::*,
+    vm::perms::VmPerms,
+};
+
+/// The alignment of the attach addresses, which is the page size on the supported architectures.
+const SHMLBA: usize = PAGE_SIZE;
+
+pub fn sys_shmat(shmid: i32, addr: Vaddr, shmflg: i32, ctx: &Context) -> Result<SyscallReturn> {
+    let flags = ShmAtFlags::from_bits_truncate(shmflg as u32);
+    debug!(
+        "[sys_shmat] shmid = {}, addr = 0x{:x}, flags = {:?}",
+        shmid, addr, flags
+    );
+
+    let addr = if addr % SHMLBA != 0 {
+        if !flags.contains(ShmAtFlags::SHM_RND) {
+            return_errno_with_message!(Errno::EINVAL, "the attach address is not aligned");
+        }
+        addr.align_down(SHMLBA)
+    } else {
+        addr
+    };
+    if addr == 0 && flags.contains(ShmAtFlags::SHM_REMAP) {
+        return_errno_with_message!(Errno::EINVAL, "SHM_REMAP requires an attach address");
+    }
+
+    let (mut requested, mut perms) = if flags.contains(ShmAtFlags::SHM_RDONLY) {
+        (0o4, VmPerms::READ)
+    } else {
+        (0o6, VmPerms::READ | VmPerms::WRITE)
+    };
+    if flags.contains(ShmAtFlags::SHM_EXEC) {
+        requested |= 0o1;
+        perms |= VmPerms::EXEC;
+    }
+
+    let segment = get_shm(shmid)?;
+    segment.check_perm(&ctx.posix_thread.credentials(), requested)?;
+
+    let user_space = ctx.user_space();
+    let root_vmar = user_space.root_vmar();
+    let mut options = root_vmar
+        .new_map(segment.size().align_up(PAGE_SIZE), perms)?
+        .is_shared(true)
+        .vmo(segment.dup_vmo()?);
+    if addr != 0 {
+        options = options.offset(addr);
+        if flags.contains(ShmAtFlags::SHM_REMAP) {
+            options = options.can_overwrite(true);
+        }
+    }
+    let map_addr = options.build()?;
+
+    segment.set_attached(ctx.process.pid());
+
+    Ok(SyscallReturn::Return(map_addr as _))
+}
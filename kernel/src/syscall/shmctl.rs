@@ -0,0 +1,165 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    ipc::{
+        key_t,
+        shm::{
+            get_shm, remove_shm, shm_usage, ShmControlCmd, ShmStat, SHMALL, SHMMAX, SHMMIN, SHMMNI,
+        },
+    },
+    prelude::*,
+    process::{Gid, Uid},
+};
+
+pub fn sys_shmctl(shmid: i32, cmd: i32, buf: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    let cmd = ShmControlCmd::try_from(cmd)?;
+    debug!(
+        "[sys_shmctl] shmid = {}, cmd = {:?}, buf = 0x{:x}",
+        shmid, cmd, buf
+    );
+
+    let credentials = ctx.posix_thread.credentials();
+
+    match cmd {
+        ShmControlCmd::IPC_INFO => {
+            let info = CShmInfo {
+                shmmax: SHMMAX as u64,
+                shmmin: SHMMIN as u64,
+                shmmni: SHMMNI as u64,
+                shmseg: SHMMNI as u64,
+                shmall: SHMALL as u64,
+                ..Default::default()
+            };
+            ctx.user_space().write_val(buf, &info)?;
+            return Ok(SyscallReturn::Return(shm_usage().max_id as _));
+        }
+        ShmControlCmd::SHM_INFO => {
+            let usage = shm_usage();
+            let info = CShmUsage {
+                used_ids: usage.used_ids as i32,
+                shm_tot: usage.total_pages as u64,
+                shm_rss: usage.total_pages as u64,
+                ..Default::default()
+            };
+            ctx.user_space().write_val(buf, &info)?;
+            return Ok(SyscallReturn::Return(usage.max_id as _));
+        }
+        ShmControlCmd::IPC_STAT | ShmControlCmd::SHM_STAT | ShmControlCmd::SHM_STAT_ANY => {
+            let segment = get_shm(shmid)?;
+            if cmd != ShmControlCmd::SHM_STAT_ANY {
+                segment.check_perm(&credentials, 0o4)?;
+            }
+            ctx.user_space()
+                .write_val(buf, &CShmidDs::from(segment.stat()))?;
+
+            // Like Linux, `SHM_STAT` and `SHM_STAT_ANY` return the ID of the segment, which is
+            // the same as the index here.
+            if cmd != ShmControlCmd::IPC_STAT {
+                return Ok(SyscallReturn::Return(shmid as _));
+            }
+        }
+        ShmControlCmd::IPC_SET => {
+            let segment = get_shm(shmid)?;
+            let shmid_ds: CShmidDs = ctx.user_space().read_val(buf)?;
+            segment.set_perm(
+                Uid::new(shmid_ds.shm_perm.uid),
+                Gid::new(shmid_ds.shm_perm.gid),
+                shmid_ds.shm_perm.mode as u16,
+                &credentials,
+            )?;
+        }
+        ShmControlCmd::IPC_RMID => remove_shm(shmid, &credentials)?,
+        ShmControlCmd::SHM_LOCK | ShmControlCmd::SHM_UNLOCK => {
+            let segment = get_shm(shmid)?;
+            segment.set_locked(cmd == ShmControlCmd::SHM_LOCK, &credentials)?;
+        }
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
+
+/// `struct ipc64_perm` in Linux.
+#[derive(Debug, Default, Clone, Copy, Pod)]
+#[repr(C)]
+struct CIpcPerm {
+    key: key_t,
+    uid: u32,
+    gid: u32,
+    cuid: u32,
+    cgid: u32,
+    mode: u32,
+    seq: u16,
+    __pad2: u16,
+    __pad3: u32,
+    __unused1: u64,
+    __unused2: u64,
+}
+
+/// `struct shmid64_ds` in Linux.
+#[derive(Debug, Default, Clone, Copy, Pod)]
+#[repr(C)]
+struct CShmidDs {
+    shm_perm: CIpcPerm,
+    shm_segsz: u64,
+    shm_atime: i64,
+    shm_dtime: i64,
+    shm_ctime: i64,
+    shm_cpid: i32,
+    shm_lpid: i32,
+    shm_nattch: u64,
+    __unused4: u64,
+    __unused5: u64,
+}
+
+impl From<ShmStat> for CShmidDs {
+    fn from(stat: ShmStat) -> Self {
+        Self {
+            shm_perm: CIpcPerm {
+                key: stat.key,
+                uid: stat.uid.into(),
+                gid: stat.gid.into(),
+                cuid: stat.cuid.into(),
+                cgid: stat.cgid.into(),
+                mode: stat.mode,
+                ..Default::default()
+            },
+            shm_segsz: stat.size as u64,
+            shm_atime: stat.atime as i64,
+            shm_dtime: stat.dtime as i64,
+            shm_ctime: stat.ctime as i64,
+            shm_cpid: stat.cpid as i32,
+            shm_lpid: stat.lpid as i32,
+            shm_nattch: stat.nattch as u64,
+            ..Default::default()
+        }
+    }
+}
+
+/// `struct shminfo64` in Linux, which is returned by `IPC_INFO`.
+#[derive(Debug, Default, Clone, Copy, Pod)]
+#[repr(C)]
+struct CShmInfo {
+    shmmax: u64,
+    shmmin: u64,
+    shmmni: u64,
+    shmseg: u64,
+    shmall: u64,
+    __unused1: u64,
+    __unused2: u64,
+    __unused3: u64,
+    __unused4: u64,
+}
+
+/// `struct shm_info` in Linux, which is returned by `SHM_INFO`.
+#[derive(Debug, Default, Clone, Copy, Pod)]
+#[repr(C)]
+struct CShmUsage {
+    used_ids: i32,
+    __pad: u32,
+    shm_tot: u64,
+    shm_rss: u64,
+    shm_swp: u64,
+    swap_attempts: u64,
+    swap_successes: u64,
+}
@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use align_ext::AlignExt;
+
+use super::SyscallReturn;
+use crate::{
+    ipc::shm::{collect_destroyed_shm, find_shm_by_vmo},
+    prelude::*,
+};
+
+pub fn sys_shmdt(addr: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    debug!("[sys_shmdt] addr = 0x{:x}", addr);
+
+    if addr % PAGE_SIZE != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the detach address is not aligned");
+    }
+
+    let user_space = ctx.user_space();
+    let root_vmar = user_space.root_vmar();
+
+    // The address must be where a shared memory segment is attached.
+    let segment = root_vmar
+        .query_vmo(addr)
+        .filter(|(range, _, vmo_offset)| range.start == addr && *vmo_offset == 0)
+        .and_then(|(_, vmo, _)| find_shm_by_vmo(&vmo))
+        .ok_or(Error::with_message(
+            Errno::EINVAL,
+            "no shared memory segment is attached at the address",
+        ))?;
+
+    // The mapping may have been split (e.g., by `mprotect`), so all the contiguous mappings of the
+    // segment are removed.
+    let end = addr + segment.size().align_up(PAGE_SIZE);
+    let mut cur = addr;
+    while cur < end {
+        let Some((range, vmo, vmo_offset)) = root_vmar.query_vmo(cur) else {
+            break;
+        };
+        if !segment.is_backed_by(&vmo) || vmo_offset != cur - addr {
+            break;
+        }
+        root_vmar.remove_mapping(cur..range.end.min(end))?;
+        cur = range.end;
+    }
+
+    segment.set_detached(ctx.process.pid());
+    drop(segment);
+    collect_destroyed_shm();
+
+    Ok(SyscallReturn::Return(0))
+}
@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    ipc::{
+        shm::{get_or_create_shm, SHM_HUGETLB},
+        IpcFlags,
+    },
+    prelude::*,
+    vm::hugetlb,
+};
+
+pub fn sys_shmget(key: i32, size: usize, shmflg: i32, ctx: &Context) -> Result<SyscallReturn> {
+    let flags = IpcFlags::from_bits_truncate(shmflg as u32);
+    let mode = (shmflg as u32 & 0o777) as u16;
+    debug!(
+        "[sys_shmget] key = {}, size = {}, flags = {:?}, mode = {:o}",
+        key, size, flags, mode
+    );
+
+    let huge_pool = if shmflg as u32 & SHM_HUGETLB != 0 {
+        // The huge page size is encoded in the high bits of the flags.
+        Some(hugetlb::pool_of_flags(shmflg as u32)?)
+    } else {
+        None
+    };
+
+    let credentials = ctx.posix_thread.credentials();
+    let (segment, is_new) = get_or_create_shm(
+        key,
+        size,
+        mode,
+        flags.contains(IpcFlags::IPC_CREAT),
+        huge_pool,
+        ctx.process.pid(),
+        &credentials,
+    )?;
+
+    if !is_new {
+        if flags.contains(IpcFlags::IPC_CREAT | IpcFlags::IPC_EXCL) {
+            return_errno_with_message!(Errno::EEXIST, "the shared memory segment already exists");
+        }
+        // Like Linux, the accesses in all the classes of `mode` are requested.
+        segment.check_perm(&credentials, (mode >> 6 | mode >> 3 | mode) & 0o7)?;
+        if size > segment.size() {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the size is larger than that of the shared memory segment"
+            );
+        }
+    }
+
+    Ok(SyscallReturn::Return(segment.id() as _))
+}
@@ -15,15 +15,19 @@ use crate::{
     events::{IoEvents, Observer},
     fs::{
         file_handle::FileLike,
-        file_table::{get_file_fast, FdFlags, FileDesc},
+        file_table::{get_file_fast, FdCreationFlags, FdFlags, FileDesc},
         utils::{CreationFlags, InodeMode, InodeType, Metadata, StatusFlags},
     },
     prelude::*,
     process::{
         posix_thread::AsPosixThread,
         signal::{
-            constants::{SIGKILL, SIGSTOP},
+            constants::{
+                SIGBUS, SIGFPE, SIGILL, SIGKILL, SIGSEGV, SIGSTOP, SIGTRAP, SI_MESGQ, SI_QUEUE,
+                SI_TIMER, SI_TKILL, SI_USER,
+            },
             sig_mask::{AtomicSigMask, SigMask},
+            sig_num::SigNum,
             signals::Signal,
             PollHandle, Pollable, Pollee, SigEvents, SigEventsFilter,
         },
@@ -66,13 +70,9 @@ pub fn sys_signalfd4(
     let flags = SignalFileFlags::from_bits(flags as u32)
         .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid flags"))?;
 
-    let fd_flags = if flags.contains(SignalFileFlags::O_CLOEXEC) {
-        FdFlags::CLOEXEC
-    } else {
-        FdFlags::empty()
-    };
-
-    let non_blocking = flags.contains(SignalFileFlags::O_NONBLOCK);
+    let creation_flags = FdCreationFlags::from_bits_truncate(flags.bits());
+    let fd_flags = creation_flags.fd_flags();
+    let non_blocking = creation_flags.is_nonblocking();
 
     let new_fd = if fd == -1 {
         create_new_signalfd(ctx, mask, non_blocking, fd_flags)?
@@ -95,7 +95,7 @@ fn create_new_signalfd(
     register_observer(ctx, &signal_file, mask)?;
 
     let file_table = ctx.thread_local.borrow_file_table();
-    let fd = file_table.unwrap().write().insert(signal_file, fd_flags);
+    let fd = file_table.unwrap().write().insert(signal_file, fd_flags)?;
     Ok(fd)
 }
 
@@ -182,6 +182,17 @@ impl SignalFile {
         Ok(())
     }
 
+    /// Registers the file as an observer of the current thread's signal queues.
+    ///
+    /// The signals are always read from the current thread, which may be different from the
+    /// thread that created the file (e.g., after `fork` or from other threads).
+    fn observe_current_thread(&self) {
+        if let Some(thread) = current_thread!().as_posix_thread() {
+            let filter = SigEventsFilter::new(self.signals_mask.load(Ordering::Relaxed));
+            thread.register_sigqueue_observer(self.weak_self.clone(), filter);
+        }
+    }
+
     fn set_non_blocking(&self, non_blocking: bool) {
         self.non_blocking.store(non_blocking, Ordering::Relaxed);
     }
@@ -236,9 +247,6 @@ impl SignalFile {
 }
 
 impl Observer<SigEvents> for SignalFile {
-    // TODO: Fix signal notifications.
-    // Child processes do not inherit the parent's observer mechanism for signal event notifications.
-    // `sys_poll` with blocking mode gets stuck if the signal is received after polling.
     fn on_events(&self, events: &SigEvents) {
         if self
             .signals_mask
@@ -252,6 +260,7 @@ impl Observer<SigEvents> for SignalFile {
 
 impl Pollable for SignalFile {
     fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.observe_current_thread();
         self.pollee
             .poll_with(mask, poller, || self.check_io_events())
     }
@@ -263,6 +272,8 @@ impl FileLike for SignalFile {
             return_errno_with_message!(Errno::EINVAL, "Buffer too small for siginfo structure");
         }
 
+        self.observe_current_thread();
+
         if self.is_non_blocking() {
             self.try_read(writer)
         } else {
@@ -309,7 +320,8 @@ impl FileLike for SignalFile {
 }
 
 impl Drop for SignalFile {
-    // TODO: Fix signal notifications. See `on_events` method.
+    // The observer may also be registered to other threads. Such registrations will be removed
+    // once the threads find that the observer has been dropped.
     fn drop(&mut self) {
         if let Some(thread) = current_thread!().as_posix_thread() {
             thread.unregister_sigqueue_observer(&self.weak_self);
@@ -346,24 +358,37 @@ trait ToSignalfdSiginfo {
 impl ToSignalfdSiginfo for Box<dyn Signal> {
     fn to_signalfd_siginfo(&self) -> SignalfdSiginfo {
         let siginfo = self.to_info();
-        SignalfdSiginfo {
-            ssi_signo: siginfo.si_signo as _,
-            ssi_errno: siginfo.si_errno,
-            ssi_code: siginfo.si_code,
-            ssi_pid: 0,
-            ssi_uid: 0,
-            ssi_fd: 0,
-            ssi_tid: 0,
-            ssi_band: 0,
-            ssi_overrun: 0,
-            ssi_trapno: 0,
-            ssi_status: 0,
-            ssi_int: 0,
-            ssi_ptr: 0,
-            ssi_utime: 0,
-            ssi_stime: 0,
-            ssi_addr: 0,
-            _pad: [0; 48],
+        let mut ssi = SignalfdSiginfo::new_zeroed();
+        ssi.ssi_signo = siginfo.si_signo as _;
+        ssi.ssi_errno = siginfo.si_errno;
+        ssi.ssi_code = siginfo.si_code;
+
+        // The meaningful fields in `siginfo_t` depend on how the signal is generated.
+        match siginfo.si_code {
+            SI_USER | SI_TKILL => {
+                ssi.ssi_pid = siginfo.si_pid();
+                ssi.ssi_uid = siginfo.si_uid().into();
+            }
+            SI_QUEUE | SI_MESGQ => {
+                ssi.ssi_pid = siginfo.si_pid();
+                ssi.ssi_uid = siginfo.si_uid().into();
+                ssi.ssi_int = siginfo.si_value().read_int();
+                ssi.ssi_ptr = siginfo.si_value().read_ptr() as u64;
+            }
+            SI_TIMER => {
+                ssi.ssi_int = siginfo.si_value().read_int();
+                ssi.ssi_ptr = siginfo.si_value().read_ptr() as u64;
+            }
+            code if code > 0 && is_fault_signal(self.num()) => {
+                ssi.ssi_addr = siginfo.si_addr() as u64;
+            }
+            _ => (),
         }
+
+        ssi
     }
 }
+
+fn is_fault_signal(sig_num: SigNum) -> bool {
+    [SIGILL, SIGTRAP, SIGBUS, SIGFPE, SIGSEGV].contains(&sig_num)
+}
@@ -2,37 +2,40 @@
 
 use super::SyscallReturn;
 use crate::{
-    fs::{file_handle::FileLike, file_table::FdFlags},
+    fs::{file_handle::FileLike, file_table::FdCreationFlags},
     net::socket::{
         ip::{datagram::DatagramSocket, stream::StreamSocket},
         netlink::{is_valid_protocol, NetlinkRouteSocket, StandardNetlinkProtocol},
-        unix::UnixStreamSocket,
+        unix::{UnixDatagramSocket, UnixStreamSocket},
         vsock::VsockStreamSocket,
     },
     prelude::*,
-    util::net::{CSocketAddrFamily, Protocol, SockFlags, SockType, SOCK_TYPE_MASK},
+    util::net::{CSocketAddrFamily, Protocol, SockType, SOCK_TYPE_MASK},
 };
 
 pub fn sys_socket(domain: i32, type_: i32, protocol: i32, ctx: &Context) -> Result<SyscallReturn> {
     let domain = CSocketAddrFamily::try_from(domain)?;
     let sock_type = SockType::try_from(type_ & SOCK_TYPE_MASK)?;
-    let sock_flags = SockFlags::from_bits_truncate(type_ & !SOCK_TYPE_MASK);
+    let sock_flags = FdCreationFlags::from_bits_truncate((type_ & !SOCK_TYPE_MASK) as u32);
     debug!(
         "domain = {:?}, sock_type = {:?}, sock_flags = {:?}",
         domain, sock_type, sock_flags
     );
-    let is_nonblocking = sock_flags.contains(SockFlags::SOCK_NONBLOCK);
+    let is_nonblocking = sock_flags.is_nonblocking();
     let file_like = match (domain, sock_type) {
         // FIXME: SOCK_SEQPACKET is added to run fcntl_test, not supported yet.
         (CSocketAddrFamily::AF_UNIX, SockType::SOCK_STREAM | SockType::SOCK_SEQPACKET) => {
             UnixStreamSocket::new(is_nonblocking) as Arc<dyn FileLike>
         }
+        (CSocketAddrFamily::AF_UNIX, SockType::SOCK_DGRAM) => {
+            UnixDatagramSocket::new(is_nonblocking) as Arc<dyn FileLike>
+        }
         (CSocketAddrFamily::AF_INET, SockType::SOCK_STREAM) => {
             let protocol = Protocol::try_from(protocol)?;
             debug!("protocol = {:?}", protocol);
             match protocol {
                 Protocol::IPPROTO_IP | Protocol::IPPROTO_TCP => {
-                    StreamSocket::new(is_nonblocking) as Arc<dyn FileLike>
+                    StreamSocket::new(is_nonblocking, ctx.process.net_ns()) as Arc<dyn FileLike>
                 }
                 _ => return_errno_with_message!(Errno::EAFNOSUPPORT, "unsupported protocol"),
             }
@@ -42,7 +45,7 @@ pub fn sys_socket(domain: i32, type_: i32, protocol: i32, ctx: &Context) -> Resu
             debug!("protocol = {:?}", protocol);
             match protocol {
                 Protocol::IPPROTO_IP | Protocol::IPPROTO_UDP => {
-                    DatagramSocket::new(is_nonblocking) as Arc<dyn FileLike>
+                    DatagramSocket::new(is_nonblocking, ctx.process.net_ns()) as Arc<dyn FileLike>
                 }
                 _ => return_errno_with_message!(Errno::EAFNOSUPPORT, "unsupported protocol"),
             }
@@ -51,9 +54,10 @@ pub fn sys_socket(domain: i32, type_: i32, protocol: i32, ctx: &Context) -> Resu
             let netlink_family = StandardNetlinkProtocol::try_from(protocol as u32);
             debug!("netlink family = {:?}", netlink_family);
             match netlink_family {
-                Ok(StandardNetlinkProtocol::ROUTE) => {
-                    Arc::new(NetlinkRouteSocket::new(is_nonblocking))
-                }
+                Ok(StandardNetlinkProtocol::ROUTE) => Arc::new(NetlinkRouteSocket::new(
+                    is_nonblocking,
+                    ctx.process.net_ns(),
+                )),
                 Ok(_) => {
                     return_errno_with_message!(
                         Errno::EAFNOSUPPORT,
@@ -79,12 +83,7 @@ pub fn sys_socket(domain: i32, type_: i32, protocol: i32, ctx: &Context) -> Resu
     let fd = {
         let file_table = ctx.thread_local.borrow_file_table();
         let mut file_table_locked = file_table.unwrap().write();
-        let fd_flags = if sock_flags.contains(SockFlags::SOCK_CLOEXEC) {
-            FdFlags::CLOEXEC
-        } else {
-            FdFlags::empty()
-        };
-        file_table_locked.insert(file_like, fd_flags)
+        file_table_locked.insert(file_like, sock_flags.fd_flags())?
     };
     Ok(SyscallReturn::Return(fd as _))
 }
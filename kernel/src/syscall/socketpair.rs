@@ -2,10 +2,13 @@
 
 use super::SyscallReturn;
 use crate::{
-    fs::file_table::{FdFlags, FileDesc},
-    net::socket::unix::UnixStreamSocket,
+    fs::{
+        file_handle::FileLike,
+        file_table::{FdCreationFlags, FileDesc},
+    },
+    net::socket::unix::{UnixDatagramSocket, UnixStreamSocket},
     prelude::*,
-    util::net::{CSocketAddrFamily, Protocol, SockFlags, SockType, SOCK_TYPE_MASK},
+    util::net::{CSocketAddrFamily, Protocol, SockType, SOCK_TYPE_MASK},
 };
 
 pub fn sys_socketpair(
@@ -17,18 +20,23 @@ pub fn sys_socketpair(
 ) -> Result<SyscallReturn> {
     let domain = CSocketAddrFamily::try_from(domain)?;
     let sock_type = SockType::try_from(type_ & SOCK_TYPE_MASK)?;
-    let sock_flags = SockFlags::from_bits_truncate(type_ & !SOCK_TYPE_MASK);
+    let sock_flags = FdCreationFlags::from_bits_truncate((type_ & !SOCK_TYPE_MASK) as u32);
     let protocol = Protocol::try_from(protocol)?;
 
     debug!(
         "domain = {:?}, sock_type = {:?}, sock_flags = {:?}, protocol = {:?}",
         domain, sock_type, sock_flags, protocol
     );
-    // TODO: deal with all sock_flags and protocol
-    let nonblocking = sock_flags.contains(SockFlags::SOCK_NONBLOCK);
-    let (socket_a, socket_b) = match (domain, sock_type) {
+    // TODO: deal with the protocol
+    let nonblocking = sock_flags.is_nonblocking();
+    let (socket_a, socket_b): (Arc<dyn FileLike>, Arc<dyn FileLike>) = match (domain, sock_type) {
         (CSocketAddrFamily::AF_UNIX, SockType::SOCK_STREAM) => {
-            UnixStreamSocket::new_pair(nonblocking)
+            let (socket_a, socket_b) = UnixStreamSocket::new_pair(nonblocking);
+            (socket_a, socket_b)
+        }
+        (CSocketAddrFamily::AF_UNIX, SockType::SOCK_DGRAM) => {
+            let (socket_a, socket_b) = UnixDatagramSocket::new_pair(nonblocking);
+            (socket_a, socket_b)
         }
         _ => return_errno_with_message!(
             Errno::EAFNOSUPPORT,
@@ -39,13 +47,13 @@ pub fn sys_socketpair(
     let socket_fds = {
         let file_table = ctx.thread_local.borrow_file_table();
         let mut file_table_locked = file_table.unwrap().write();
-        let fd_flags = if sock_flags.contains(SockFlags::SOCK_CLOEXEC) {
-            FdFlags::CLOEXEC
-        } else {
-            FdFlags::empty()
-        };
-        let fd_a = file_table_locked.insert(socket_a, fd_flags);
-        let fd_b = file_table_locked.insert(socket_b, fd_flags);
+        let fd_flags = sock_flags.fd_flags();
+        let fd_a = file_table_locked.insert(socket_a, fd_flags)?;
+        let fd_b = file_table_locked
+            .insert(socket_b, fd_flags)
+            .inspect_err(|_| {
+                file_table_locked.close_file(fd_a).unwrap();
+            })?;
         SocketFds(fd_a, fd_b)
     };
 
@@ -8,6 +8,7 @@ use crate::{
         utils::Metadata,
     },
     prelude::*,
+    process::namespace::user_ns::UserNamespace,
     syscall::constants::MAX_FILENAME_LEN,
     time::timespec_t,
 };
@@ -18,7 +19,7 @@ pub fn sys_fstat(fd: FileDesc, stat_buf_ptr: Vaddr, ctx: &Context) -> Result<Sys
     let mut file_table = ctx.thread_local.borrow_file_table_mut();
     let file = get_file_fast!(&mut file_table, fd);
 
-    let stat = Stat::from(file.metadata());
+    let stat = Stat::new(file.metadata(), &ctx.posix_thread.credentials().user_ns());
     ctx.user_space().write_val(stat_buf_ptr, &stat)?;
 
     Ok(SyscallReturn::Return(0))
@@ -65,14 +66,15 @@ pub fn sys_fstatat(
     let dentry = {
         let filename = filename.to_string_lossy();
         let fs_path = FsPath::new(dirfd, filename.as_ref())?;
-        let fs = ctx.posix_thread.fs().resolver().read();
+        let fs_ref = ctx.posix_thread.fs().read();
+        let fs = fs_ref.resolver().read();
         if flags.contains(StatFlags::AT_SYMLINK_NOFOLLOW) {
             fs.lookup_no_follow(&fs_path)?
         } else {
             fs.lookup(&fs_path)?
         }
     };
-    let stat = Stat::from(dentry.metadata());
+    let stat = Stat::new(dentry.metadata(), &ctx.posix_thread.credentials().user_ns());
     user_space.write_val(stat_buf_ptr, &stat)?;
     Ok(SyscallReturn::Return(0))
 }
@@ -113,15 +115,17 @@ pub struct Stat {
     __unused: [i64; 3],
 }
 
-impl From<Metadata> for Stat {
-    fn from(info: Metadata) -> Self {
+impl Stat {
+    /// Creates the `stat` structure, where the owner and the group are seen from the user
+    /// namespace.
+    fn new(info: Metadata, user_ns: &UserNamespace) -> Self {
         Self {
             st_dev: info.dev,
             st_ino: info.ino,
             st_nlink: info.nlinks,
             st_mode: info.type_ as u32 | info.mode.bits() as u32,
-            st_uid: info.uid.into(),
-            st_gid: info.gid.into(),
+            st_uid: user_ns.uid_from_kernel_or_overflow(info.uid),
+            st_gid: user_ns.gid_from_kernel_or_overflow(info.gid),
             __pad0: 0,
             st_rdev: info.rdev,
             st_size: info.size as isize,
@@ -6,6 +6,7 @@ use super::SyscallReturn;
 use crate::{
     fs::{device::DeviceId, file_table::FileDesc, fs_resolver::FsPath, utils::Metadata},
     prelude::*,
+    process::namespace::user_ns::UserNamespace,
     syscall::constants::MAX_FILENAME_LEN,
 };
 
@@ -17,8 +18,22 @@ pub fn sys_statx(
     statx_buf_ptr: Vaddr,
     ctx: &Context,
 ) -> Result<SyscallReturn> {
-    let user_space = ctx.user_space();
-    let filename = user_space.read_cstring(filename_ptr, MAX_FILENAME_LEN)?;
+    let filename = ctx
+        .user_space()
+        .read_cstring(filename_ptr, MAX_FILENAME_LEN)?;
+    do_statx(dirfd, &filename, flags, mask, statx_buf_ptr, ctx)?;
+    Ok(SyscallReturn::Return(0))
+}
+
+/// Writes the `statx` structure of the file at `filename` relative to `dirfd` to the user space.
+pub fn do_statx(
+    dirfd: FileDesc,
+    filename: &CStr,
+    flags: u32,
+    mask: u32,
+    statx_buf_ptr: Vaddr,
+    ctx: &Context,
+) -> Result<()> {
     let flags = StatxFlags::from_bits(flags)
         .ok_or(Error::with_message(Errno::EINVAL, "invalid statx flags"))?;
     let mask = StatxMask::from_bits_truncate(mask);
@@ -47,7 +62,8 @@ pub fn sys_statx(
     let dentry = {
         let filename = filename.to_string_lossy();
         let fs_path = FsPath::new(dirfd, filename.as_ref())?;
-        let fs = ctx.posix_thread.fs().resolver().read();
+        let fs_ref = ctx.posix_thread.fs().read();
+        let fs = fs_ref.resolver().read();
         if flags.contains(StatxFlags::AT_SYMLINK_NOFOLLOW) {
             fs.lookup_no_follow(&fs_path)?
         } else {
@@ -55,10 +71,10 @@ pub fn sys_statx(
         }
     };
 
-    let statx = Statx::from(dentry.metadata());
+    let statx = Statx::new(dentry.metadata(), &ctx.posix_thread.credentials().user_ns());
 
-    user_space.write_val(statx_buf_ptr, &statx)?;
-    Ok(SyscallReturn::Return(0))
+    ctx.user_space().write_val(statx_buf_ptr, &statx)?;
+    Ok(())
 }
 
 /// Structures for the extended file attribute retrieval system call statx.
@@ -114,8 +130,10 @@ pub struct Statx {
     __spare3: [u64; 12],
 }
 
-impl From<Metadata> for Statx {
-    fn from(info: Metadata) -> Self {
+impl Statx {
+    /// Creates the `statx` structure, where the owner and the group are seen from the user
+    /// namespace.
+    fn new(info: Metadata, user_ns: &UserNamespace) -> Self {
         let devid = DeviceId::from(info.dev);
         let rdevid = DeviceId::from(info.rdev);
         Self {
@@ -124,8 +142,8 @@ impl From<Metadata> for Statx {
             stx_blksize: info.blk_size as u32,
             stx_attributes: 0,
             stx_nlink: info.nlinks as u32,
-            stx_uid: info.uid.into(),
-            stx_gid: info.gid.into(),
+            stx_uid: user_ns.uid_from_kernel_or_overflow(info.uid),
+            stx_gid: user_ns.gid_from_kernel_or_overflow(info.gid),
             stx_mode: info.type_ as u16 | info.mode.bits(),
             __spare0: [0; 1],
             stx_ino: info.ino,
@@ -37,6 +37,7 @@ pub fn sys_symlinkat(
         let fs_path = FsPath::new(dirfd, linkpath.as_ref())?;
         ctx.posix_thread
             .fs()
+            .read()
             .resolver()
             .read()
             .lookup_dir_and_new_basename(&fs_path, false)?
@@ -0,0 +1,251 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `syslog` system call, with which the user space (e.g., `dmesg`) reads and clears the
+//! kernel log and controls the console log level.
+//!
+//! The records are formatted as in Linux:
+//! ```text
+//! <priority>[seconds.microseconds] message
+//! ```
+
+use core::fmt::Write;
+
+use aster_logger::{
+    clear_records, console_level, first_record_seq, first_uncleared_record_seq, next_record_seq,
+    read_record, set_console_level, LOG_BUF_LEN, MAX_CONSOLE_LEVEL, MAX_RECORD_LEN,
+    MIN_CONSOLE_LEVEL,
+};
+
+use super::SyscallReturn;
+use crate::{
+    device::kernel_log_pollee,
+    events::IoEvents,
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        signal::{PollHandle, Pollable},
+    },
+};
+
+pub fn sys_syslog(action: i32, buf: Vaddr, len: i32, ctx: &Context) -> Result<SyscallReturn> {
+    let action = SyslogAction::try_from(action)
+        .map_err(|_| Error::with_message(Errno::EINVAL, "the syslog action is invalid"))?;
+    debug!("action = {:?}, buf = 0x{:x}, len = {}", action, buf, len);
+
+    // Like Linux with `kernel.dmesg_restrict` disabled, reading all records and getting the
+    // buffer size are not restricted.
+    if !matches!(action, SyslogAction::ReadAll | SyslogAction::SizeBuffer) {
+        let credentials = ctx.posix_thread.credentials();
+        if !credentials.has_capability(CapSet::SYSLOG)
+            && !credentials.has_capability(CapSet::SYS_ADMIN)
+        {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "the current thread does not have the CAP_SYSLOG capability"
+            );
+        }
+    }
+
+    let res = match action {
+        SyslogAction::Close | SyslogAction::Open => 0,
+        SyslogAction::Read | SyslogAction::ReadAll | SyslogAction::ReadClear => {
+            if buf == 0 || len < 0 {
+                return_errno_with_message!(Errno::EINVAL, "the buffer is invalid");
+            }
+            if len == 0 {
+                return Ok(SyscallReturn::Return(0));
+            }
+
+            let user_space = ctx.user_space();
+            let mut writer = user_space.writer(buf, len as usize)?;
+            match action {
+                SyslogAction::Read => SYSLOG_READER.read(&mut writer)?,
+                _ => read_all(&mut writer, action == SyslogAction::ReadClear)?,
+            }
+        }
+        SyslogAction::Clear => {
+            clear_records();
+            0
+        }
+        SyslogAction::ConsoleOff => {
+            let mut saved_level = SAVED_CONSOLE_LEVEL.lock();
+            if saved_level.is_none() {
+                *saved_level = Some(console_level());
+            }
+            set_console_level(MIN_CONSOLE_LEVEL);
+            0
+        }
+        SyslogAction::ConsoleOn => {
+            if let Some(level) = SAVED_CONSOLE_LEVEL.lock().take() {
+                set_console_level(level);
+            }
+            0
+        }
+        SyslogAction::ConsoleLevel => {
+            if !(MIN_CONSOLE_LEVEL as i32..=MAX_CONSOLE_LEVEL as i32).contains(&len) {
+                return_errno_with_message!(Errno::EINVAL, "the console log level is invalid");
+            }
+            // Setting the level turns the console on implicitly.
+            let mut saved_level = SAVED_CONSOLE_LEVEL.lock();
+            set_console_level(len as u8);
+            *saved_level = None;
+            0
+        }
+        SyslogAction::SizeUnread => SYSLOG_READER.unread_len(),
+        SyslogAction::SizeBuffer => LOG_BUF_LEN,
+    };
+
+    Ok(SyscallReturn::Return(res as _))
+}
+
+/// The console log level before the console is turned off, or `None` if the console is on.
+static SAVED_CONSOLE_LEVEL: Mutex<Option<u8>> = Mutex::new(None);
+
+/// The reader of [`SyslogAction::Read`], whose position is shared by all callers.
+static SYSLOG_READER: SyslogReader = SyslogReader { seq: Mutex::new(0) };
+
+struct SyslogReader {
+    /// The sequence number of the next record to read.
+    seq: Mutex<u64>,
+}
+
+impl SyslogReader {
+    /// Reads the records until the buffer is full, waiting if there are no records to read.
+    fn read(&self, writer: &mut VmWriter) -> Result<usize> {
+        self.wait_events(IoEvents::IN, None, || self.try_read(writer))
+    }
+
+    fn try_read(&self, writer: &mut VmWriter) -> Result<usize> {
+        let mut seq = self.seq.lock();
+
+        let mut text = vec![0; MAX_RECORD_LEN];
+        let mut read_len = 0;
+        // The records that have been overwritten are skipped. Unlike the other actions, the
+        // records that have been cleared are still read, as in Linux.
+        *seq = (*seq).max(first_record_seq());
+        while let Some(line) = format_record(*seq, &mut text) {
+            if line.len() > writer.avail() {
+                // Like Linux, the record is truncated if it is the first one to read. Otherwise,
+                // it is left for the next read.
+                if read_len > 0 {
+                    break;
+                }
+                let len = writer.avail();
+                writer.write_fallible(&mut line.as_bytes()[..len].into())?;
+                *seq += 1;
+                return Ok(len);
+            }
+
+            writer.write_fallible(&mut line.as_bytes().into())?;
+            read_len += line.len();
+            *seq += 1;
+        }
+
+        if read_len == 0 {
+            return_errno_with_message!(Errno::EAGAIN, "there are no new records");
+        }
+        Ok(read_len)
+    }
+
+    /// Returns the length of the records that have not been read.
+    fn unread_len(&self) -> usize {
+        let seq = (*self.seq.lock()).max(first_record_seq());
+
+        let mut text = vec![0; MAX_RECORD_LEN];
+        (seq..next_record_seq())
+            .filter_map(|seq| format_record(seq, &mut text))
+            .map(|line| line.len())
+            .sum()
+    }
+}
+
+impl Pollable for SyslogReader {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        if let Some(poller) = poller {
+            kernel_log_pollee().register_poller(poller, mask);
+        }
+
+        let seq = (*self.seq.lock()).max(first_record_seq());
+        if seq < next_record_seq() {
+            IoEvents::IN & mask
+        } else {
+            IoEvents::empty()
+        }
+    }
+}
+
+/// Reads the newest records that fit in the buffer without changing the position of
+/// [`SYSLOG_READER`], and clears all records if `clear` is true.
+fn read_all(writer: &mut VmWriter, clear: bool) -> Result<usize> {
+    let first_seq = first_uncleared_record_seq();
+    let next_seq = next_record_seq();
+    let mut text = vec![0; MAX_RECORD_LEN];
+
+    // Find the oldest record from which the records fit in the buffer.
+    let mut start_seq = next_seq;
+    let mut total_len = 0;
+    while start_seq > first_seq {
+        let Some(line) = format_record(start_seq - 1, &mut text) else {
+            break;
+        };
+        if total_len + line.len() > writer.avail() {
+            break;
+        }
+        total_len += line.len();
+        start_seq -= 1;
+    }
+
+    let mut read_len = 0;
+    for seq in start_seq..next_seq {
+        let Some(line) = format_record(seq, &mut text) else {
+            break;
+        };
+        if line.len() > writer.avail() {
+            break;
+        }
+        writer.write_fallible(&mut line.as_bytes().into())?;
+        read_len += line.len();
+    }
+
+    if clear {
+        clear_records();
+    }
+
+    Ok(read_len)
+}
+
+/// Formats the record with the sequence number, using `text` as the scratch buffer.
+///
+/// This function returns `None` if the record does not exist, e.g., it has been overwritten.
+fn format_record(seq: u64, text: &mut [u8]) -> Option<String> {
+    let info = read_record(seq, text).filter(|info| info.seq == seq)?;
+
+    let mut line = String::new();
+    let secs = info.timestamp.as_secs();
+    let micros = info.timestamp.subsec_micros();
+    // Like Linux, each line of a multi-line record has its own prefix.
+    for text_line in String::from_utf8_lossy(&text[..info.len]).split('\n') {
+        let _ = writeln!(
+            line,
+            "<{}>[{:5}.{:06}] {}",
+            info.priority, secs, micros, text_line
+        );
+    }
+    Some(line)
+}
+
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+enum SyslogAction {
+    Close = 0,
+    Open = 1,
+    Read = 2,
+    ReadAll = 3,
+    ReadClear = 4,
+    Clear = 5,
+    ConsoleOff = 6,
+    ConsoleOn = 7,
+    ConsoleLevel = 8,
+    SizeUnread = 9,
+    SizeBuffer = 10,
+}
@@ -8,26 +8,45 @@ use crate::{
             sig_num::SigNum,
             signals::user::{UserSignal, UserSignalKind},
         },
-        tgkill, Pid,
+        tgkill, tkill, Pid,
     },
     thread::Tid,
 };
 
 /// tgkill send a signal to a thread with pid as its thread id, and tgid as its thread group id.
 pub fn sys_tgkill(tgid: Pid, tid: Tid, sig_num: u8, ctx: &Context) -> Result<SyscallReturn> {
-    let sig_num = if sig_num == 0 {
-        None
-    } else {
-        Some(SigNum::try_from(sig_num)?)
-    };
-
-    debug!("tgid = {}, pid = {}, sig_num = {:?}", tgid, tid, sig_num);
-
-    let signal = sig_num.map(|sig_num| {
-        let pid = ctx.process.pid();
-        let uid = ctx.posix_thread.credentials().ruid();
-        UserSignal::new(sig_num, UserSignalKind::Tkill, pid, uid)
-    });
+    debug!("tgid = {}, pid = {}, sig_num = {}", tgid, tid, sig_num);
+
+    if (tgid as i32) <= 0 || (tid as i32) <= 0 {
+        return_errno_with_message!(Errno::EINVAL, "the thread group ID or thread ID is invalid");
+    }
+
+    let signal = new_tkill_signal(sig_num, ctx)?;
     tgkill(tid, tgid, signal, ctx)?;
     Ok(SyscallReturn::Return(0))
 }
+
+/// tkill send a signal to a thread with pid as its thread id.
+pub fn sys_tkill(tid: Tid, sig_num: u8, ctx: &Context) -> Result<SyscallReturn> {
+    debug!("pid = {}, sig_num = {}", tid, sig_num);
+
+    if (tid as i32) <= 0 {
+        return_errno_with_message!(Errno::EINVAL, "the thread ID is invalid");
+    }
+
+    let signal = new_tkill_signal(sig_num, ctx)?;
+    tkill(tid, signal, ctx)?;
+    Ok(SyscallReturn::Return(0))
+}
+
+fn new_tkill_signal(sig_num: u8, ctx: &Context) -> Result<Option<UserSignal>> {
+    if sig_num == 0 {
+        return Ok(None);
+    }
+
+    let sig_num = SigNum::try_from(sig_num)?;
+    let pid = ctx.process.pid();
+    let uid = ctx.posix_thread.credentials().ruid();
+    let signal = UserSignal::new(sig_num, UserSignalKind::Tkill, pid, uid);
+    Ok(Some(signal))
+}
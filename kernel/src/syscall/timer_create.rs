@@ -13,7 +13,7 @@ use crate::{
             c_types::{sigevent_t, SigNotify},
             constants::SIGALRM,
             sig_num::SigNum,
-            signals::kernel::KernelSignal,
+            signals::{kernel::KernelSignal, timer::TimerSignal},
         },
     },
     syscall::ClockId,
@@ -42,10 +42,12 @@ pub fn sys_timer_create(
     let sent_signal: Box<dyn Fn() + Send + Sync + 'static> = {
         // If `sigevent_addr` is NULL, use the default method (like `sys_alarm`) to send signal.
         if sigevent_addr == 0 {
-            let process = current_process.clone();
+            let process = Arc::downgrade(&current_process);
             let signal = KernelSignal::new(SIGALRM);
             Box::new(move || {
-                process.enqueue_signal(signal);
+                if let Some(process) = process.upgrade() {
+                    process.enqueue_signal(signal);
+                }
             })
         // Determine the timeout action through `sigevent`.
         } else {
@@ -56,21 +58,20 @@ pub fn sys_timer_create(
                 // Do nothing when the timer is expired.
                 SigNotify::SIGEV_NONE => Box::new(|| {}),
                 // Send a signal to the current process when the timer is expired.
-                SigNotify::SIGEV_SIGNAL => {
-                    let process = current_process.clone();
-                    let signal = KernelSignal::new(SigNum::try_from(signo as u8)?);
+                //
+                // The `sigev_function` of `SIGEV_THREAD` is run by a thread that is created in
+                // the user space (e.g., by glibc), which asks for `SIGEV_THREAD_ID` instead. So,
+                // like Linux, `SIGEV_THREAD` merely means sending a signal to the process here.
+                SigNotify::SIGEV_SIGNAL | SigNotify::SIGEV_THREAD => {
+                    let process = Arc::downgrade(&current_process);
+                    let signal =
+                        TimerSignal::new(SigNum::try_from(signo as u8)?, sig_event.sigev_value);
                     Box::new(move || {
-                        process.enqueue_signal(signal);
+                        if let Some(process) = process.upgrade() {
+                            process.enqueue_signal(signal);
+                        }
                     })
                 }
-                // Spawn a posix thread to run the `sigev_function`, which is stored in
-                // `sig_event.sigev_un._sigev_thread`.
-                //
-                // TODO: enable this instructions. Currently the system does not provide an API to spawn
-                // a posix thread to run a specified function.
-                SigNotify::SIGEV_THREAD => {
-                    unimplemented!()
-                }
                 // Send a signal to the specified thread when the timer is expired.
                 SigNotify::SIGEV_THREAD_ID => {
                     let tid = sig_event.sigev_un.read_tid() as u32;
@@ -84,7 +85,8 @@ pub fn sys_timer_create(
                             "target thread should belong to current process"
                         );
                     }
-                    let signal = KernelSignal::new(SigNum::try_from(signo as u8)?);
+                    let signal =
+                        TimerSignal::new(SigNum::try_from(signo as u8)?, sig_event.sigev_value);
                     Box::new(move || {
                         if let Some(thread) = thread.as_posix_thread() {
                             thread.enqueue_signal(Box::new(signal));
@@ -106,7 +108,7 @@ pub fn sys_timer_create(
 
     let timer = create_timer(clockid, func, ctx)?;
 
-    let timer_id = current_process.timer_manager().add_posix_timer(timer);
+    let timer_id = current_process.timer_manager().add_posix_timer(timer)?;
     ctx.user_space().write_val(timer_id_addr, &timer_id)?;
     Ok(SyscallReturn::Return(0))
 }
@@ -147,10 +149,13 @@ where
                     .ok_or_else(|| crate::Error::with_message(Errno::EINVAL, "invalid clock id"))?;
                 let process_timer_manager = process.timer_manager();
                 match clock_type {
-                    DynamicClockType::Profiling => process_timer_manager.create_prof_timer(func),
+                    // The scheduling clock measures the CPU time like the profiling clock, but
+                    // the CPU time is only accounted at the granularity of ticks.
+                    DynamicClockType::Profiling | DynamicClockType::Scheduling => {
+                        process_timer_manager.create_prof_timer(func)
+                    }
                     DynamicClockType::Virtual => process_timer_manager.create_virtual_timer(func),
-                    // TODO: support scheduling clock and fd clock.
-                    _ => unimplemented!(),
+                    DynamicClockType::FD => unreachable!(),
                 }
             }
             DynamicClockIdInfo::Tid(tid, clock_type) => {
@@ -158,12 +163,17 @@ where
                     .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid clock id"))?;
                 let posix_thread = thread.as_posix_thread().unwrap();
                 match clock_type {
-                    DynamicClockType::Profiling => posix_thread.create_prof_timer(func),
+                    DynamicClockType::Profiling | DynamicClockType::Scheduling => {
+                        posix_thread.create_prof_timer(func)
+                    }
                     DynamicClockType::Virtual => posix_thread.create_virtual_timer(func),
-                    _ => unimplemented!(),
+                    DynamicClockType::FD => unreachable!(),
                 }
             }
-            DynamicClockIdInfo::Fd(_) => unimplemented!(),
+            // TODO: Support the clocks of character devices, e.g., PTP hardware clocks.
+            DynamicClockIdInfo::Fd(_) => {
+                return_errno_with_message!(Errno::EINVAL, "FD clocks are not supported")
+            }
         }
     };
     Ok(timer)
@@ -43,12 +43,13 @@ pub fn sys_timer_settime(
         // Clear previous timer
         timer.cancel();
     } else {
-        let timeout = if flags == 0 {
-            Timeout::After(expire_time)
-        } else if flags == TIMER_ABSTIME {
+        // Like Linux, the unknown flags are ignored.
+        let timeout = if flags & TIMER_ABSTIME != 0 {
+            // FIXME: The expiration time should be converted from the time namespace of the
+            // current process, but POSIX timers do not record the ID of their clocks.
             Timeout::When(expire_time)
         } else {
-            unreachable!()
+            Timeout::After(expire_time)
         };
         timer.set_timeout(timeout);
     }
@@ -1,8 +1,8 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use super::SyscallReturn;
+use super::{clock_gettime::ClockId, SyscallReturn};
 use crate::{
-    fs::file_table::FdFlags,
+    fs::file_table::FdCreationFlags,
     prelude::*,
     time::{
         clockid_t,
@@ -13,18 +13,23 @@ use crate::{
 pub fn sys_timerfd_create(clockid: clockid_t, flags: i32, ctx: &Context) -> Result<SyscallReturn> {
     let flags = TFDFlags::from_bits(flags as u32)
         .ok_or_else(|| Error::with_message(Errno::EINVAL, "unknown flags"))?;
+    debug!("clockid = {}, flags = {:?}", clockid, flags);
+
+    // Timer file descriptors only support a few system-wide clocks.
+    if !matches!(
+        ClockId::try_from(clockid),
+        Ok(ClockId::CLOCK_REALTIME | ClockId::CLOCK_MONOTONIC | ClockId::CLOCK_BOOTTIME)
+    ) {
+        return_errno_with_message!(Errno::EINVAL, "the clock is not supported by timerfd");
+    }
 
     let timerfd_file = TimerfdFile::new(clockid, flags, ctx)?;
 
     let fd = {
         let file_table = ctx.thread_local.borrow_file_table();
         let mut file_table_locked = file_table.unwrap().write();
-        let fd_flags = if flags.contains(TFDFlags::TFD_CLOEXEC) {
-            FdFlags::CLOEXEC
-        } else {
-            FdFlags::empty()
-        };
-        file_table_locked.insert(Arc::new(timerfd_file), fd_flags)
+        let fd_flags = FdCreationFlags::from_bits_truncate(flags.bits()).fd_flags();
+        file_table_locked.insert(Arc::new(timerfd_file), fd_flags)?
     };
 
     Ok(SyscallReturn::Return(fd as _))
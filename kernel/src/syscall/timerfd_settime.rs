@@ -9,6 +9,9 @@ use crate::{
     time::{itimerspec_t, timer::Timeout, timerfd::TimerfdFile, timespec_t, TIMER_ABSTIME},
 };
 
+const TFD_TIMER_ABSTIME: i32 = TIMER_ABSTIME;
+const TFD_TIMER_CANCEL_ON_SET: i32 = 2;
+
 pub fn sys_timerfd_settime(
     fd: FileDesc,
     flags: i32,
@@ -23,6 +26,10 @@ pub fn sys_timerfd_settime(
         .downcast_ref::<TimerfdFile>()
         .ok_or_else(|| Error::with_message(Errno::EINVAL, "the fd is not a timerfd"))?;
 
+    if flags & !(TFD_TIMER_ABSTIME | TFD_TIMER_CANCEL_ON_SET) != 0 {
+        return_errno_with_message!(Errno::EINVAL, "invalid flags");
+    }
+
     let user_space = ctx.user_space();
     let new_itimerspec = user_space.read_val::<itimerspec_t>(new_itimerspec_addr)?;
     let interval = Duration::try_from(new_itimerspec.it_interval)?;
@@ -45,16 +52,20 @@ pub fn sys_timerfd_settime(
     // when the timer is rearmed.
     timerfd_file.clear_ticks();
 
-    const TFD_TIMER_CANCEL_ON_SET: i32 = 2;
+    let is_abstime = flags & TFD_TIMER_ABSTIME != 0;
+    // Like Linux, only absolute timers can be canceled when the real time is set.
+    timerfd_file.set_cancel_on_set(is_abstime && flags & TFD_TIMER_CANCEL_ON_SET != 0);
+
     if expire_time != Duration::ZERO {
-        let timeout = if flags == 0 {
-            Timeout::After(expire_time)
-        } else if flags == TIMER_ABSTIME {
+        let timeout = if is_abstime {
+            // The expiration time is given in the time namespace of the current process.
+            let expire_time = ctx
+                .process
+                .time_ns()
+                .ns_to_host(timerfd_file.clockid(), expire_time);
             Timeout::When(expire_time)
-        } else if flags == TFD_TIMER_CANCEL_ON_SET {
-            unimplemented!()
         } else {
-            unreachable!()
+            Timeout::After(expire_time)
         };
         timer.set_timeout(timeout);
     }
@@ -4,6 +4,7 @@ use super::SyscallReturn;
 use crate::{
     fs::fs_resolver::{FsPath, AT_FDCWD},
     prelude::*,
+    process::credentials::capabilities::CapSet,
     syscall::constants::MAX_FILENAME_LEN,
 };
 
@@ -14,6 +15,17 @@ pub fn sys_umount(path_addr: Vaddr, flags: u64, ctx: &Context) -> Result<Syscall
 
     umount_flags.check_unsupported_flags()?;
 
+    if !ctx
+        .posix_thread
+        .credentials()
+        .has_capability(CapSet::SYS_ADMIN)
+    {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "the current thread does not have the CAP_SYS_ADMIN capability"
+        );
+    }
+
     let path = path.to_string_lossy();
     if path.is_empty() {
         return_errno_with_message!(Errno::ENOENT, "path is empty");
@@ -23,11 +35,17 @@ pub fn sys_umount(path_addr: Vaddr, flags: u64, ctx: &Context) -> Result<Syscall
     let target_dentry = if umount_flags.contains(UmountFlags::UMOUNT_NOFOLLOW) {
         ctx.posix_thread
             .fs()
+            .read()
             .resolver()
             .read()
             .lookup_no_follow(&fs_path)?
     } else {
-        ctx.posix_thread.fs().resolver().read().lookup(&fs_path)?
+        ctx.posix_thread
+            .fs()
+            .read()
+            .resolver()
+            .read()
+            .lookup(&fs_path)?
     };
 
     target_dentry.unmount()?;
@@ -36,6 +36,7 @@ pub fn sys_unlinkat(
         let fs_path = FsPath::new(dirfd, path.as_ref())?;
         ctx.posix_thread
             .fs()
+            .read()
             .resolver()
             .read()
             .lookup_dir_and_base_name(&fs_path)?
@@ -0,0 +1,114 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use ostd::sync::RwArc;
+
+use super::SyscallReturn;
+use crate::{
+    net::namespace::NetNamespace,
+    prelude::*,
+    process::{credentials::capabilities::CapSet, CloneFlags},
+};
+
+pub fn sys_unshare(raw_flags: u64, ctx: &Context) -> Result<SyscallReturn> {
+    let mut flags = CloneFlags::from_bits(raw_flags as u32)
+        .filter(|flags| SUPPORTED_FLAGS.contains(*flags))
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid flags"))?;
+    debug!("flags = {:?}", flags);
+
+    // Like Linux, a new user namespace can only be entered if the credentials and the file
+    // system information are not shared with other threads.
+    if flags.contains(CloneFlags::CLONE_NEWUSER) {
+        flags |= CloneFlags::CLONE_THREAD | CloneFlags::CLONE_FS;
+    }
+
+    if flags.intersects(NAMESPACE_FLAGS) {
+        return_errno_with_message!(Errno::EINVAL, "namespaces are not supported");
+    }
+
+    // The thread group, the signal handlers, and the virtual memory cannot be unshared with other
+    // threads in the same process. Unsharing them is only allowed if there are no such threads.
+    if flags.intersects(CloneFlags::CLONE_THREAD | CloneFlags::CLONE_SIGHAND | CloneFlags::CLONE_VM)
+        && ctx.process.tasks().lock().as_slice().len() > 1
+    {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the thread group, the signal handlers, or the virtual memory is shared"
+        );
+    }
+
+    if flags.contains(CloneFlags::CLONE_NEWUSER) {
+        ctx.posix_thread.credentials_mut().enter_new_user_ns()?;
+    }
+
+    if flags.contains(CloneFlags::CLONE_FS) {
+        let mut fs = ctx.posix_thread.fs().write();
+        // Only the thread itself can share its FS information with new threads, so the count
+        // cannot increase here.
+        if Arc::strong_count(&fs) > 1 {
+            *fs = Arc::new(fs.as_ref().clone());
+        }
+    }
+
+    if flags.contains(CloneFlags::CLONE_FILES) {
+        let mut file_table = ctx.thread_local.borrow_file_table_mut();
+        if file_table.unwrap().get().is_none() {
+            let new_file_table = RwArc::new(file_table.unwrap().read().clone());
+            *ctx.posix_thread.file_table().lock() = Some(new_file_table.clone_ro());
+            file_table.replace(new_file_table);
+        }
+    }
+
+    if flags.contains(CloneFlags::CLONE_NEWTIME) {
+        if !ctx
+            .posix_thread
+            .credentials()
+            .has_capability(CapSet::SYS_ADMIN)
+        {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "the current thread does not have the CAP_SYS_ADMIN capability"
+            );
+        }
+        // Only the children of the current process will enter the new time namespace.
+        let time_ns = ctx.process.time_ns_for_children().new_child();
+        ctx.process.set_time_ns_for_children(time_ns);
+    }
+
+    if flags.contains(CloneFlags::CLONE_NEWNET) {
+        let credentials = ctx.posix_thread.credentials();
+        let user_ns = credentials.user_ns();
+        if !credentials.has_capability_in(CapSet::SYS_ADMIN, &user_ns) {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "the current thread does not have the CAP_SYS_ADMIN capability"
+            );
+        }
+        // FIXME: Linux only moves the current thread to the new namespace, but the network
+        // namespace is shared by all the threads in the same process here.
+        ctx.process.set_net_ns(NetNamespace::new(user_ns));
+    }
+
+    if flags.contains(CloneFlags::CLONE_SYSVSEM) {
+        // The SEM_UNDO semantics are not supported, so there is nothing to unshare.
+        warn!("CLONE_SYSVSEM is not supported now");
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
+
+const NAMESPACE_FLAGS: CloneFlags = CloneFlags::CLONE_NEWNS
+    .union(CloneFlags::CLONE_NEWCGROUP)
+    .union(CloneFlags::CLONE_NEWUTS)
+    .union(CloneFlags::CLONE_NEWIPC)
+    .union(CloneFlags::CLONE_NEWPID);
+
+const SUPPORTED_FLAGS: CloneFlags = CloneFlags::CLONE_THREAD
+    .union(CloneFlags::CLONE_SIGHAND)
+    .union(CloneFlags::CLONE_VM)
+    .union(CloneFlags::CLONE_FS)
+    .union(CloneFlags::CLONE_FILES)
+    .union(CloneFlags::CLONE_SYSVSEM)
+    .union(CloneFlags::CLONE_NEWTIME)
+    .union(CloneFlags::CLONE_NEWUSER)
+    .union(CloneFlags::CLONE_NEWNET)
+    .union(NAMESPACE_FLAGS);
@@ -20,32 +20,34 @@ pub fn sys_wait4(
         wait_pid as i32, exit_status_ptr, wait_options
     );
     debug!("wait4 current pid = {}", ctx.process.pid());
+
+    // Like Linux, waiting for the exited children is implied, and the children cannot be left
+    // in the waitable state.
+    if wait_options.intersects(WaitOptions::WEXITED | WaitOptions::WNOWAIT) {
+        return_errno_with_message!(Errno::EINVAL, "the wait option is only valid for waitid");
+    }
+    let wait_options = wait_options | WaitOptions::WEXITED;
+
     let process_filter = ProcessFilter::from_id(wait_pid as _);
 
-    let waited_process =
+    let waited_child =
         wait_child_exit(process_filter, wait_options, ctx).map_err(|err| match err.error() {
             Errno::EINTR => Error::new(Errno::ERESTARTSYS),
             _ => err,
         })?;
-    let Some(process) = waited_process else {
+    let Some((process, wait_status)) = waited_child else {
         return Ok(SyscallReturn::Return(0 as _));
     };
 
-    let (return_pid, exit_code) = (process.pid(), process.status().exit_code());
     if exit_status_ptr != 0 {
         ctx.user_space()
-            .write_val(exit_status_ptr as _, &exit_code)?;
+            .write_val(exit_status_ptr as _, &wait_status.as_u32())?;
     }
 
     if rusage_addr != 0 {
-        let rusage = rusage_t {
-            ru_utime: process.prof_clock().user_clock().read_time().into(),
-            ru_stime: process.prof_clock().kernel_clock().read_time().into(),
-            ..Default::default()
-        };
-
+        let rusage = rusage_t::of_process(&process);
         ctx.user_space().write_val(rusage_addr, &rusage)?;
     }
 
-    Ok(SyscallReturn::Return(return_pid as _))
+    Ok(SyscallReturn::Return(process.pid() as _))
 }
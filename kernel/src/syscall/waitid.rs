@@ -1,30 +1,71 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use super::SyscallReturn;
+use super::{getrusage::rusage_t, SyscallReturn};
 use crate::{
     prelude::*,
-    process::{wait_child_exit, ProcessFilter, WaitOptions},
+    process::{
+        posix_thread::AsPosixThread,
+        signal::{c_types::siginfo_t, constants::SIGCHLD},
+        wait_child_exit, ProcessFilter, WaitOptions,
+    },
 };
 
 pub fn sys_waitid(
     which: u64,
     upid: u64,
-    _infoq_addr: u64,
+    infop_addr: Vaddr,
     options: u64,
-    _rusage_addr: u64,
+    rusage_addr: Vaddr,
     ctx: &Context,
 ) -> Result<SyscallReturn> {
-    // FIXME: what does infoq and rusage use for?
     let process_filter = ProcessFilter::from_which_and_id(which, upid as _)?;
     let wait_options = WaitOptions::from_bits(options as u32)
         .ok_or(Error::with_message(Errno::EINVAL, "invalid options"))?;
+    debug!(
+        "process_filter = {:?}, infop_addr = {:#x}, wait_options = {:?}, rusage_addr = {:#x}",
+        process_filter, infop_addr, wait_options, rusage_addr
+    );
 
-    let waited_process =
+    if !wait_options
+        .intersects(WaitOptions::WEXITED | WaitOptions::WSTOPPED | WaitOptions::WCONTINUED)
+    {
+        return_errno_with_message!(Errno::EINVAL, "no status changes are waited for");
+    }
+
+    let waited_child =
         wait_child_exit(process_filter, wait_options, ctx).map_err(|err| match err.error() {
             Errno::EINTR => Error::new(Errno::ERESTARTSYS),
             _ => err,
         })?;
 
-    let pid = waited_process.map_or(0, |process| process.pid());
-    Ok(SyscallReturn::Return(pid as _))
+    let Some((process, wait_status)) = waited_child else {
+        // With `WNOHANG`, the information is zeroed if no children have changed their status.
+        if infop_addr != 0 {
+            ctx.user_space()
+                .write_val(infop_addr, &siginfo_t::new_zeroed())?;
+        }
+        return Ok(SyscallReturn::Return(0));
+    };
+
+    if infop_addr != 0 {
+        let (code, status) = wait_status.as_code_and_status();
+        let uid = process
+            .main_thread()
+            .as_posix_thread()
+            .unwrap()
+            .credentials()
+            .ruid();
+
+        let mut info = siginfo_t::new(SIGCHLD, code);
+        info.set_si_pid_uid(process.pid(), uid);
+        info.set_si_status(status);
+        ctx.user_space().write_val(infop_addr, &info)?;
+    }
+
+    if rusage_addr != 0 {
+        let rusage = rusage_t::of_process(&process);
+        ctx.user_space().write_val(rusage_addr, &rusage)?;
+    }
+
+    Ok(SyscallReturn::Return(0))
 }
@@ -37,5 +37,6 @@ pub fn sys_write(
         _ => err,
     })?;
 
+    ctx.process.io_stats().account_write(write_len);
     Ok(SyscallReturn::Return(write_len as _))
 }
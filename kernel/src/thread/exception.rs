@@ -2,13 +2,20 @@
 
 #![expect(unused_variables)]
 
+use core::sync::atomic::Ordering;
+
 use aster_rights::Full;
+use aster_trace::{declare_tracepoint, tracepoint};
 use ostd::cpu::context::{CpuExceptionInfo, UserContext};
 
 use crate::{
     current_userspace,
     prelude::*,
-    process::signal::signals::fault::FaultSignal,
+    process::signal::{
+        constants::SIGKILL,
+        sig_action::SigAction,
+        signals::{fault::FaultSignal, kernel::KernelSignal, Signal},
+    },
     vm::{page_fault_handler::PageFaultHandler, perms::VmPerms, vmar::Vmar},
 };
 
@@ -26,6 +33,12 @@ pub struct PageFaultInfo {
     pub required_perms: VmPerms,
 }
 
+declare_tracepoint! {
+    /// Emitted when a page fault on a user address is handled, with the error number if it
+    /// fails or zero if it succeeds.
+    pub(super) static PAGE_FAULT: exceptions:page_fault(address: hex, required_perms: hex, errno);
+}
+
 /// We can't handle most exceptions, just send self a fault signal before return to user space.
 pub fn handle_exception(ctx: &Context, context: &UserContext) {
     let trap_info = context.trap_information();
@@ -34,8 +47,15 @@ pub fn handle_exception(ctx: &Context, context: &UserContext) {
     if let Ok(page_fault_info) = PageFaultInfo::try_from(trap_info) {
         let user_space = ctx.user_space();
         let root_vmar = user_space.root_vmar();
-        if handle_page_fault_from_vmar(root_vmar, &page_fault_info).is_ok() {
-            return;
+        match handle_page_fault_from_vmar(root_vmar, &page_fault_info) {
+            Ok(()) => return,
+            // Like the OOM killer in Linux, kill the process if no memory can be allocated for
+            // it (e.g., the memory limit of its cgroup is hit).
+            Err(err) if err.error() == Errno::ENOMEM => {
+                ctx.process.enqueue_signal(KernelSignal::new(SIGKILL));
+                return;
+            }
+            Err(_) => (),
         }
     }
 
@@ -46,20 +66,42 @@ pub fn handle_exception(ctx: &Context, context: &UserContext) {
 fn handle_page_fault_from_vmar(
     root_vmar: &Vmar<Full>,
     page_fault_info: &PageFaultInfo,
-) -> core::result::Result<(), ()> {
-    if let Err(e) = root_vmar.handle_page_fault(page_fault_info) {
-        warn!(
-            "page fault handler failed: addr: 0x{:x}, err: {:?}",
-            page_fault_info.address, e
-        );
-        return Err(());
-    }
-    Ok(())
+) -> Result<()> {
+    let result = root_vmar
+        .handle_page_fault(page_fault_info)
+        .inspect_err(|e| {
+            warn!(
+                "page fault handler failed: addr: 0x{:x}, err: {:?}",
+                page_fault_info.address, e
+            );
+        });
+    tracepoint!(
+        PAGE_FAULT,
+        page_fault_info.address,
+        page_fault_info.required_perms.bits(),
+        result.as_ref().map_or_else(|err| err.error() as u32, |_| 0)
+    );
+    result
 }
 
-/// generate a fault signal for current process.
+/// Generates a fault signal for the current thread.
+///
+/// A fault signal cannot be blocked or ignored, otherwise the thread will run into the same fault
+/// again after returning to the user space. So, like Linux, if the signal is blocked or ignored,
+/// it is unblocked and its disposition is reset to the default one.
 fn generate_fault_signal(trap_info: &CpuExceptionInfo, ctx: &Context) {
     let signal = FaultSignal::from(trap_info);
+    let sig_num = signal.num();
+
+    let sig_mask = ctx.posix_thread.sig_mask();
+    let old_mask = sig_mask.load(Ordering::Relaxed);
+    let mut sig_dispositions = ctx.process.sig_dispositions().lock();
+    if old_mask.contains(sig_num) || matches!(sig_dispositions.get(sig_num), SigAction::Ign) {
+        sig_dispositions.set_default(sig_num);
+        sig_mask.store(old_mask - sig_num, Ordering::Relaxed);
+    }
+    drop(sig_dispositions);
+
     ctx.posix_thread.enqueue_signal(Box::new(signal));
 }
 
@@ -78,4 +120,5 @@ fn log_trap_info(trap_info: &CpuExceptionInfo) {
 
 pub(super) fn page_fault_handler(info: &CpuExceptionInfo) -> core::result::Result<(), ()> {
     handle_page_fault_from_vmar(current_userspace!().root_vmar(), &info.try_into().unwrap())
+        .map_err(|_| ())
 }
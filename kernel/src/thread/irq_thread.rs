@@ -0,0 +1,34 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The IRQ threads.
+//!
+//! The threaded IRQ handlers registered with [`IrqLine::on_active_threaded`] run in dedicated
+//! kernel threads. The threads are scheduled in the real-time scheduling class with the FIFO
+//! policy, so that the interrupts are handled before the ordinary threads run.
+//!
+//! [`IrqLine::on_active_threaded`]: ostd::trap::IrqLine::on_active_threaded
+
+use ostd::trap::IrqThreadPriority;
+
+use crate::{
+    prelude::*,
+    sched::{RealTimePolicy, RealTimePriority, SchedPolicy},
+    thread::kernel_thread::ThreadOptions,
+};
+
+/// Injects the spawner of the IRQ threads.
+pub fn init() {
+    ostd::trap::inject_irq_thread_spawner(spawn_irq_thread);
+}
+
+fn spawn_irq_thread(thread_fn: Box<dyn FnOnce() + Send>, priority: IrqThreadPriority) {
+    // `IrqThreadPriority` has the same range as `RealTimePriority`.
+    let rt_prio = RealTimePriority::new(priority.get());
+
+    ThreadOptions::new(thread_fn)
+        .sched_policy(SchedPolicy::RealTime {
+            rt_prio,
+            rt_policy: RealTimePolicy::Fifo,
+        })
+        .spawn();
+}
@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The softirq daemons.
+//!
+//! Each CPU has a daemon thread that processes the softirqs that are raised
+//! too frequently to be processed in the bottom half of the interrupts, e.g.,
+//! under heavy network traffic. The daemon is an ordinary thread, so it takes
+//! turns with the other threads to run on the CPU.
+
+use ostd::{
+    cpu::{all_cpus, current_cpu_racy},
+    sync::WaitQueue,
+};
+use spin::Once;
+
+use crate::{
+    prelude::*,
+    sched::{Nice, SchedPolicy},
+    thread::{kernel_thread::ThreadOptions, Thread},
+};
+
+/// The wait queues of the daemons, indexed by the CPU IDs.
+static DAEMON_WAIT_QUEUES: Once<Vec<WaitQueue>> = Once::new();
+
+/// Spawns the softirq daemons on all the CPUs.
+pub fn init() {
+    DAEMON_WAIT_QUEUES.call_once(|| all_cpus().map(|_| WaitQueue::new()).collect());
+
+    for cpu in all_cpus() {
+        let wait_queue = &DAEMON_WAIT_QUEUES.get().unwrap()[cpu.as_usize()];
+        ThreadOptions::new(move || loop {
+            // The condition is checked on the bound CPU.
+            wait_queue.wait_until(|| aster_softirq::has_pending().then_some(()));
+            aster_softirq::process_pending_in_task();
+            Thread::yield_now();
+        })
+        .cpu_affinity(cpu.into())
+        .sched_policy(SchedPolicy::Fair(Nice::default()))
+        .spawn();
+    }
+
+    aster_softirq::register_daemon_waker(wake_current_daemon);
+}
+
+fn wake_current_daemon() {
+    // The local IRQs are disabled, so the CPU ID is up-to-date.
+    let cpu = current_cpu_racy();
+    if let Some(wait_queues) = DAEMON_WAIT_QUEUES.get() {
+        wait_queues[cpu.as_usize()].wake_one();
+    }
+}
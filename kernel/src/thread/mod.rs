@@ -16,7 +16,9 @@ use crate::{
 };
 
 pub mod exception;
+pub mod irq_thread;
 pub mod kernel_thread;
+pub mod ksoftirqd;
 pub mod oops;
 pub mod status;
 pub mod task;
@@ -27,18 +29,25 @@ pub type Tid = u32;
 fn post_schedule_handler() {
     let task = Task::current().unwrap();
     let Some(thread_local) = task.as_thread_local() else {
+        // Kernel threads never access the user space, so the TLB flushes of
+        // the previously activated user space can be deferred.
+        ostd::mm::vm_space::enter_lazy_tlb();
         return;
     };
 
     let root_vmar = thread_local.root_vmar().borrow();
     if let Some(vmar) = root_vmar.as_ref() {
         vmar.vm_space().activate()
+    } else {
+        ostd::mm::vm_space::enter_lazy_tlb();
     }
 }
 
 pub(super) fn init() {
     ostd::task::inject_post_schedule_handler(post_schedule_handler);
     ostd::arch::trap::inject_user_page_fault_handler(exception::page_fault_handler);
+    aster_trace::register_tracepoints(&[&exception::PAGE_FAULT]);
+    oops::init();
 }
 
 /// A thread is a wrapper on top of task.
@@ -105,6 +114,11 @@ impl Thread {
         self.status.load(Ordering::Acquire).is_stopped()
     }
 
+    /// Returns whether the thread is sleeping, i.e., waiting to be woken up.
+    pub fn is_sleeping(&self) -> bool {
+        self.task.upgrade().is_some_and(|task| task.is_sleeping())
+    }
+
     /// Stops the thread if it is running.
     ///
     /// If the previous status is not [`ThreadStatus::Running`], this function
@@ -4,8 +4,14 @@
 //!
 //! In Asterinas, a Rust panic leads to a kernel "oops". A kernel oops behaves
 //! as an exceptional control flow event. If kernel oopses happened too many
-//! times, the kernel panics and the system gets halted. Kernel oops are per-
-//! thread, so one thread's oops does not affect other threads.
+//! times, the kernel panics and the system gets halted. Like Linux, an oops
+//! kills the process (or ends the kernel thread) where it happens, so it does
+//! not affect other processes, unless `panic_on_oops` is set via the kernel
+//! command line or `/proc/sys/kernel/panic_on_oops`.
+//!
+//! A fatal CPU exception in the kernel (e.g., an unexpected page fault) is also
+//! an oops if it occurs in the process context. Since the stack cannot be
+//! unwound across the trap, the process is killed in the exception handler.
 //!
 //! Though we can recover from the Rust panics. It is generally not recommended
 //! to make Rust panics as a general exception handling mechanism. Handling
@@ -18,6 +24,7 @@ use alloc::{
     sync::Arc,
 };
 use core::{
+    fmt,
     result::Result,
     sync::atomic::{AtomicBool, AtomicUsize, Ordering},
 };
@@ -25,11 +32,35 @@ use core::{
 use ostd::{cpu::PinCurrentCpu, panic, task::disable_preempt};
 
 use super::Thread;
+use crate::process::{
+    posix_thread::{do_exit_group, AsPosixThread},
+    signal::constants::SIGKILL,
+    TermStatus,
+};
+
+crate::kernel_param! {
+    /// Halts the system on a kernel oops, instead of killing the process that causes it.
+    ///
+    /// It can be changed at runtime via `/proc/sys/kernel/panic_on_oops`.
+    static PANIC_ON_OOPS_PARAM: bool = ("panic_on_oops", false);
+}
+
+static PANIC_ON_OOPS: AtomicBool = AtomicBool::new(false);
 
-// TODO: Control the kernel commandline parsing from the kernel crate.
-// In Linux it can be dynamically changed by writing to
-// `/proc/sys/kernel/panic`.
-static PANIC_ON_OOPS: AtomicBool = AtomicBool::new(true);
+pub(super) fn init() {
+    PANIC_ON_OOPS.store(PANIC_ON_OOPS_PARAM.get(), Ordering::Relaxed);
+    ostd::trap::inject_kernel_exception_handler(kernel_exception_handler);
+}
+
+/// Returns whether the kernel panics on oops.
+pub fn panic_on_oops() -> bool {
+    PANIC_ON_OOPS.load(Ordering::Relaxed)
+}
+
+/// Sets whether the kernel panics on oops.
+pub fn set_panic_on_oops(panic_on_oops: bool) {
+    PANIC_ON_OOPS.store(panic_on_oops, Ordering::Relaxed);
+}
 
 /// The kernel "oops" information.
 pub struct OopsInfo {
@@ -59,19 +90,46 @@ where
             let info = err.downcast::<OopsInfo>().unwrap();
 
             log::error!("Oops! {}", info.message);
-
-            let count = OOPS_COUNT.fetch_add(1, Ordering::Relaxed);
-            if count >= MAX_OOPS_COUNT {
-                // Too many oops. Abort the kernel.
-                log::error!("Too many oops. The kernel panics.");
-                panic::abort();
-            }
+            count_oops();
 
             Err(*info)
         }
     }
 }
 
+/// Handles a fatal CPU exception in the kernel.
+///
+/// If the exception occurs in a POSIX thread, the process is killed as an oops.
+/// The exception handler of OSTD then terminates the current task, since the
+/// trapped code cannot continue.
+fn kernel_exception_handler(message: fmt::Arguments<'_>) -> bool {
+    if panic_on_oops() {
+        return false;
+    }
+    let Some(thread) = Thread::current() else {
+        return false;
+    };
+    if thread.as_posix_thread().is_none() {
+        return false;
+    }
+
+    log::error!("Oops! {}", message);
+    panic::print_stack_trace();
+    count_oops();
+
+    do_exit_group(TermStatus::Killed(SIGKILL));
+    true
+}
+
+fn count_oops() {
+    let count = OOPS_COUNT.fetch_add(1, Ordering::Relaxed);
+    if count >= MAX_OOPS_COUNT {
+        // Too many oops. Abort the kernel.
+        log::error!("Too many oops. The kernel panics.");
+        panic::abort();
+    }
+}
+
 /// The maximum number of oops allowed before the kernel panics.
 ///
 /// It is the same as Linux's default value.
@@ -84,14 +142,17 @@ fn panic_handler(info: &core::panic::PanicInfo) -> ! {
     let message = info.message();
 
     if let Some(thread) = Thread::current() {
-        let panic_on_oops = PANIC_ON_OOPS.load(Ordering::Relaxed);
-        if !panic_on_oops && info.can_unwind() {
+        if !panic_on_oops() && info.can_unwind() {
             // TODO: eliminate the need for heap allocation.
             let message = if let Some(location) = info.location() {
                 format!("{} at {}:{}", message, location.file(), location.line())
             } else {
                 message.to_string()
             };
+            // Print the call trace as a Linux oops does, since the stack is
+            // unwound when the panic is caught.
+            panic::print_stack_trace();
+            panic::emit_oops_report(info);
             // Raise the panic and expect it to be caught.
             panic::begin_panic(Box::new(OopsInfo { message, thread }));
         }
@@ -125,6 +186,7 @@ fn panic_handler(info: &core::panic::PanicInfo) -> ! {
     } else {
         log::error!("Backtrace is disabled.");
     }
+    panic::emit_panic_report(info);
 
     panic::abort();
 }
@@ -12,8 +12,10 @@ use crate::{
     current_userspace,
     prelude::*,
     process::{
-        posix_thread::{AsPosixThread, AsThreadLocal, ThreadLocal},
-        signal::handle_pending_signal,
+        cgroup::freeze_current,
+        posix_thread::{do_exit_group, AsPosixThread, AsThreadLocal, ThreadLocal},
+        signal::{constants::SIGKILL, handle_pending_signal},
+        TermStatus,
     },
     syscall::handle_syscall,
     thread::{exception::handle_exception, AsThread},
@@ -89,6 +91,8 @@ pub fn create_new_user_task(
                 break;
             }
             handle_pending_signal(user_ctx, &ctx, syscall_number);
+            // A thread in a frozen cgroup waits here until the cgroup is thawed.
+            freeze_current(&ctx);
             // If current is suspended, wait for a signal to wake up self
             while current_thread.is_stopped() {
                 Thread::yield_now();
@@ -104,9 +108,11 @@ pub fn create_new_user_task(
     }
 
     TaskOptions::new(|| {
-        // TODO: If a kernel "oops" is caught, we should kill the entire
-        // process rather than just ending the thread.
-        let _ = oops::catch_panics_as_oops(user_task_entry);
+        // The entire process is killed if a kernel "oops" is caught, since
+        // the process may be left in an inconsistent state.
+        if oops::catch_panics_as_oops(user_task_entry).is_err() {
+            do_exit_group(TermStatus::Killed(SIGKILL));
+        }
     })
     .data(thread_ref)
     .local_data(thread_local)
@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use super::{work_item::WorkItem, WorkQueue};
+use crate::{
+    prelude::*,
+    time::{clocks::JIFFIES_TIMER_MANAGER, timer::Timeout, Timer},
+};
+
+/// A work item that is submitted to a work queue after a delay.
+pub struct DelayedWorkItem {
+    work_item: Arc<WorkItem>,
+    timer: Arc<Timer>,
+}
+
+impl DelayedWorkItem {
+    /// Creates a delayed work item that will be submitted to `work_queue`.
+    pub fn new(work_func: Box<dyn Fn() + Send + Sync>, work_queue: &Arc<WorkQueue>) -> Arc<Self> {
+        let work_item = WorkItem::new(work_func);
+        let timer = {
+            let work_item = work_item.clone();
+            let work_queue = Arc::downgrade(work_queue);
+            JIFFIES_TIMER_MANAGER.get().unwrap().create_timer(move || {
+                if let Some(work_queue) = work_queue.upgrade() {
+                    work_queue.enqueue(work_item.clone());
+                }
+            })
+        };
+        Arc::new(Self { work_item, timer })
+    }
+
+    /// Submits the work item to the work queue after the delay.
+    ///
+    /// If the work item is waiting to be submitted, the previous delay is
+    /// discarded. If the work item is still pending in the work queue when the
+    /// delay expires, it is not submitted again.
+    pub fn schedule(&self, delay: Duration) {
+        self.timer.set_timeout(Timeout::After(delay));
+    }
+
+    /// Cancels the submission that is waiting for the delay to expire.
+    ///
+    /// The work item that has been submitted is not affected.
+    pub fn cancel(&self) {
+        self.timer.cancel();
+    }
+
+    /// Returns the underlying work item.
+    pub fn work_item(&self) -> &Arc<WorkItem> {
+        &self.work_item
+    }
+}
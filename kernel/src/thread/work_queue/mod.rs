@@ -63,15 +63,38 @@
 //! my_queue.enqueue(work_item);
 //!
 //! ```
+//!
+//! A work queue can limit the number of its work items that run concurrently,
+//! delay the submission of work items, and wait for all the submitted work
+//! items to be done.
+//!
+//! ```rust
+//! use core::time::Duration;
+//! use crate::thread::work_queue::{DelayedWorkItem, WorkQueue};
+//!
+//! // At most one work item in the queue runs at a time.
+//! let ordered_queue = WorkQueue::with_max_active(Arc::downgrade(pool), 1);
+//!
+//! let delayed_work = DelayedWorkItem::new(Box::new(deferred_task), &ordered_queue);
+//! delayed_work.schedule(Duration::from_millis(10));
+//!
+//! // Wait for the work items in the queue to be done.
+//! ordered_queue.flush();
+//! ```
 
+pub use delayed_work::DelayedWorkItem;
 use intrusive_collections::linked_list::LinkedList;
-use ostd::cpu::{CpuId, CpuSet};
+use ostd::{
+    cpu::{CpuId, CpuSet},
+    sync::WaitQueue,
+};
 use spin::Once;
 use work_item::{WorkItem, WorkItemAdapter};
 use worker_pool::WorkerPool;
 
 use crate::prelude::*;
 
+mod delayed_work;
 mod simple_scheduler;
 pub mod work_item;
 pub mod worker;
@@ -105,26 +128,55 @@ pub fn submit_work_item(work_item: Arc<WorkItem>, work_priority: WorkPriority) -
     }
 }
 
+/// Creates a work queue that processes its work items one by one with a
+/// global worker pool.
+pub fn create_ordered_work_queue(work_priority: WorkPriority) -> Arc<WorkQueue> {
+    let worker_pool = match work_priority {
+        WorkPriority::High => WORKERPOOL_HIGH_PRI.get().unwrap(),
+        WorkPriority::Normal => WORKERPOOL_NORMAL.get().unwrap(),
+    };
+    WorkQueue::with_max_active(Arc::downgrade(worker_pool), 1)
+}
+
 /// A work queue maintains a series of work items to be handled
 /// asynchronously in a process context.
 pub struct WorkQueue {
     worker_pool: Weak<WorkerPool>,
     inner: SpinLock<WorkQueueInner>,
+    /// The maximum number of work items in this queue that run concurrently.
+    max_active: usize,
+    /// The wait queue for the flushers of this queue.
+    flush_queue: WaitQueue,
 }
 
 struct WorkQueueInner {
     pending_work_items: LinkedList<WorkItemAdapter>,
+    /// The number of work items that are being processed.
+    nr_active: usize,
 }
 
 impl WorkQueue {
     /// Create a `WorkQueue` and specify a `WorkerPool` to
     /// process the submitted `WorkItems`.
     pub fn new(worker_pool: Weak<WorkerPool>) -> Arc<Self> {
+        Self::with_max_active(worker_pool, usize::MAX)
+    }
+
+    /// Create a `WorkQueue` whose work items are processed by at most
+    /// `max_active` workers at the same time.
+    ///
+    /// A queue with `max_active` being one processes its work items one by
+    /// one in the submission order.
+    pub fn with_max_active(worker_pool: Weak<WorkerPool>, max_active: usize) -> Arc<Self> {
+        assert!(max_active > 0);
         let queue = Arc::new(WorkQueue {
             worker_pool: worker_pool.clone(),
             inner: SpinLock::new(WorkQueueInner {
                 pending_work_items: LinkedList::new(WorkItemAdapter::NEW),
+                nr_active: 0,
             }),
+            max_active,
+            flush_queue: WaitQueue::new(),
         });
         worker_pool
             .upgrade()
@@ -149,12 +201,20 @@ impl WorkQueue {
 
     /// Request a pending work item. The `request_cpu` indicates the CPU where
     /// the calling worker is located.
+    ///
+    /// The caller must call [`Self::finish`] after processing the returned work item.
     fn dequeue(&self, request_cpu: CpuId) -> Option<Arc<WorkItem>> {
         let mut inner = self.inner.disable_irq().lock();
+        if inner.nr_active >= self.max_active {
+            return None;
+        }
+
         let mut cursor = inner.pending_work_items.front_mut();
         while let Some(item) = cursor.get() {
             if item.is_valid_cpu(request_cpu) {
-                return cursor.remove();
+                let item = cursor.remove();
+                inner.nr_active += 1;
+                return item;
             }
 
             cursor.move_next();
@@ -163,13 +223,54 @@ impl WorkQueue {
         None
     }
 
+    /// Marks a work item returned by [`Self::dequeue`] as done.
+    fn finish(&self) {
+        let mut inner = self.inner.disable_irq().lock();
+        inner.nr_active -= 1;
+        let has_pending = !inner.pending_work_items.is_empty();
+        let is_idle = inner.nr_active == 0 && !has_pending;
+        drop(inner);
+
+        if is_idle {
+            self.flush_queue.wake_all();
+        } else if has_pending {
+            // The work items may have been held back by `max_active`, so the
+            // idle workers must be woken up to process them.
+            self.wake_workers();
+        }
+    }
+
+    /// Wakes up a worker on each CPU where a pending work item can be processed.
+    fn wake_workers(&self) {
+        let Some(worker_pool) = self.worker_pool.upgrade() else {
+            return;
+        };
+        for cpu_id in worker_pool.cpu_set().iter() {
+            if self.has_pending_work_items(cpu_id) {
+                worker_pool.wake_worker(cpu_id);
+            }
+        }
+    }
+
     fn has_pending_work_items(&self, request_cpu: CpuId) -> bool {
-        self.inner
-            .disable_irq()
-            .lock()
-            .pending_work_items
-            .iter()
-            .any(|item| item.is_valid_cpu(request_cpu))
+        let inner = self.inner.disable_irq().lock();
+        inner.nr_active < self.max_active
+            && inner
+                .pending_work_items
+                .iter()
+                .any(|item| item.is_valid_cpu(request_cpu))
+    }
+
+    /// Waits until all the work items in this queue are done.
+    ///
+    /// The work items submitted during the wait are also waited for. This
+    /// method must not be called by the work items in this queue, which would
+    /// then wait for themselves.
+    pub fn flush(&self) {
+        self.flush_queue.wait_until(|| {
+            let inner = self.inner.disable_irq().lock();
+            (inner.nr_active == 0 && inner.pending_work_items.is_empty()).then_some(())
+        });
     }
 }
 
@@ -202,3 +303,62 @@ pub enum WorkPriority {
     High,
     Normal,
 }
+
+#[cfg(ktest)]
+mod test {
+    use core::time::Duration;
+
+    use ostd::prelude::*;
+
+    use super::*;
+
+    fn new_work_item() -> Arc<WorkItem> {
+        WorkItem::new(Box::new(|| {}))
+    }
+
+    #[ktest]
+    fn max_active_and_flush() {
+        // The pool is not run, so the work items are only processed by hand.
+        let pool = WorkerPool::new(WorkPriority::Normal, CpuSet::new_full());
+        let queue = WorkQueue::with_max_active(Arc::downgrade(&pool), 1);
+        let cpu = CpuId::bsp();
+
+        let first = new_work_item();
+        let second = new_work_item();
+        assert!(queue.enqueue(first.clone()));
+        assert!(!queue.enqueue(first.clone()));
+        assert!(queue.enqueue(second.clone()));
+
+        let item = queue.dequeue(cpu).unwrap();
+        assert!(Arc::ptr_eq(&item, &first));
+        assert!(!queue.has_pending_work_items(cpu));
+        assert!(queue.dequeue(cpu).is_none());
+
+        queue.finish();
+        assert!(queue.has_pending_work_items(cpu));
+        let item = queue.dequeue(cpu).unwrap();
+        assert!(Arc::ptr_eq(&item, &second));
+        queue.finish();
+
+        // The queue is idle, so this does not block.
+        queue.flush();
+    }
+
+    #[ktest]
+    fn cancel_delayed_work() {
+        crate::time::clocks::init_for_ktest();
+
+        let pool = WorkerPool::new(WorkPriority::Normal, CpuSet::new_full());
+        let queue = WorkQueue::new(Arc::downgrade(&pool));
+        let cpu = CpuId::bsp();
+
+        let delayed_work = DelayedWorkItem::new(Box::new(|| {}), &queue);
+        delayed_work.schedule(Duration::from_secs(60));
+        delayed_work.cancel();
+        assert!(!queue.has_pending_work_items(cpu));
+
+        // The work item can still be submitted directly.
+        assert!(queue.enqueue(delayed_work.work_item().clone()));
+        assert!(queue.has_pending_work_items(cpu));
+    }
+}
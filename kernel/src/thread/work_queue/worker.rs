@@ -84,9 +84,12 @@ impl Worker {
             let Some(worker_pool) = worker_pool else {
                 break;
             };
-            if let Some(work_item) = worker_pool.fetch_pending_work_item(self.bound_cpu) {
+            if let Some((work_queue, work_item)) =
+                worker_pool.fetch_pending_work_item(self.bound_cpu)
+            {
                 work_item.set_processing();
                 work_item.call_work_func();
+                work_queue.finish();
                 worker_pool.set_heartbeat(self.bound_cpu, true);
             } else {
                 if self.is_destroying() {
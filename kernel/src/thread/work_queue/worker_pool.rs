@@ -174,11 +174,16 @@ impl WorkerPool {
         &self.cpu_set
     }
 
-    pub(super) fn fetch_pending_work_item(&self, request_cpu: CpuId) -> Option<Arc<WorkItem>> {
+    /// Fetches a pending work item and the work queue that it belongs to.
+    ///
+    /// The caller must call [`WorkQueue::finish`] after processing the work item.
+    pub(super) fn fetch_pending_work_item(
+        &self,
+        request_cpu: CpuId,
+    ) -> Option<(Arc<WorkQueue>, Arc<WorkItem>)> {
         for work_queue in self.work_queues.disable_irq().lock().iter() {
-            let item = work_queue.dequeue(request_cpu);
-            if item.is_some() {
-                return item;
+            if let Some(item) = work_queue.dequeue(request_cpu) {
+                return Some((work_queue.clone(), item));
             }
         }
         None
@@ -8,9 +8,9 @@ use ostd::{cpu::PinCurrentCpu, cpu_local, sync::SpinLock, task::disable_preempt,
 use paste::paste;
 use spin::Once;
 
-use crate::time::{
-    self, system_time::START_TIME_AS_DURATION, timer::TimerManager, Clock, SystemTime,
-};
+use crate::time::{self, timer::TimerManager, Clock, SystemTime};
+#[cfg(ktest)]
+use crate::time::{system_time::START_TIME_AS_DURATION, START_TIME};
 
 /// The Clock that reads the jiffies, and turn the counter into `Duration`.
 pub struct JiffiesClock {
@@ -62,7 +62,7 @@ impl MonotonicClock {
 
 /// `RealTimeCoarseClock` is a coarse-grained version of a real-time clock.
 ///
-/// This clock will maintain a record to `RealTimeClock`. This record
+/// This clock will maintain a record to `MonotonicClock`. This record
 /// will be updated during each system timer interruption. Reading this clock
 /// will directly reads the value of the record instead of calculating the time
 /// based on the clocksource. Hence it is faster but less accurate.
@@ -88,7 +88,8 @@ impl RealTimeCoarseClock {
 
 /// `MonotonicCoarseClock` is a coarse-grained version of the monotonic clock.
 ///
-/// This clock is based on [`RealTimeCoarseClock`].
+/// This clock shares the record with [`RealTimeCoarseClock`], so it is not affected when the
+/// real time is set.
 ///
 /// Usually it will not be used to create a timer.
 pub struct MonotonicCoarseClock {
@@ -165,13 +166,18 @@ impl Clock for MonotonicClock {
 
 impl Clock for RealTimeCoarseClock {
     fn read_time(&self) -> Duration {
-        *Self::current_ref().get().unwrap().disable_irq().lock()
+        let monotonic_time = *Self::current_ref().get().unwrap().disable_irq().lock();
+        time::real_time_of(monotonic_time)
     }
 }
 
 impl Clock for MonotonicCoarseClock {
     fn read_time(&self) -> Duration {
-        RealTimeCoarseClock::get().read_time() - *START_TIME_AS_DURATION.get().unwrap()
+        *RealTimeCoarseClock::current_ref()
+            .get()
+            .unwrap()
+            .disable_irq()
+            .lock()
     }
 }
 
@@ -283,14 +289,14 @@ fn init_jiffies_clock_manager() {
 }
 
 fn update_coarse_clock() {
-    let real_time = RealTimeClock::get().read_time();
+    let monotonic_time = read_monotonic_time();
     let current = RealTimeCoarseClock::current_ref().get().unwrap();
-    *current.disable_irq().lock() = real_time;
+    *current.disable_irq().lock() = monotonic_time;
 }
 
 fn init_coarse_clock() {
-    let real_time = RealTimeClock::get().read_time();
-    RealTimeCoarseClock::current_ref().call_once(|| SpinLock::new(real_time));
+    let monotonic_time = read_monotonic_time();
+    RealTimeCoarseClock::current_ref().call_once(|| SpinLock::new(monotonic_time));
     time::softirq::register_callback(update_coarse_clock);
 }
 
@@ -302,18 +308,26 @@ pub(super) fn init() {
 }
 
 #[cfg(ktest)]
-/// Init `CLOCK_REALTIME_MANAGER` for process-related ktests.
+/// Init `CLOCK_REALTIME_MANAGER` and `CLOCK_MONOTONIC_MANAGER` for process-related ktests.
 ///
 /// TODO: `ktest` may require a feature that allows the registration of initialization functions
 /// to avoid functions like this one.
 pub fn init_for_ktest() {
     // If `spin::Once` has initialized, this closure will not be executed.
+    START_TIME.call_once(|| SystemTime::UNIX_EPOCH);
+    START_TIME_AS_DURATION.call_once(|| Duration::ZERO);
     for cpu in ostd::cpu::all_cpus() {
         CLOCK_REALTIME_MANAGER.get_on_cpu(cpu).call_once(|| {
             let clock = RealTimeClock { _private: () };
             TimerManager::new(Arc::new(clock))
         });
     }
+    for cpu in ostd::cpu::all_cpus() {
+        CLOCK_MONOTONIC_MANAGER.get_on_cpu(cpu).call_once(|| {
+            let clock = MonotonicClock { _private: () };
+            TimerManager::new(Arc::new(clock))
+        });
+    }
     CLOCK_REALTIME_COARSE_INSTANCE.call_once(|| Arc::new(RealTimeCoarseClock { _private: () }));
     RealTimeCoarseClock::current_ref().call_once(|| SpinLock::new(Duration::from_secs(0)));
     JIFFIES_TIMER_MANAGER.call_once(|| {
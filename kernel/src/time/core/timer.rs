@@ -100,7 +100,10 @@ impl Timer {
     /// Return the current expired time of this timer.
     pub fn expired_time(&self) -> Duration {
         let timer_callback = self.timer_callback.disable_irq().lock().upgrade();
-        timer_callback.map_or(Duration::ZERO, |timer_callback| timer_callback.expired_time)
+        // A cancelled callback may still be kept by the `TimerManager` until it is expired.
+        timer_callback
+            .filter(|timer_callback| !timer_callback.is_cancelled())
+            .map_or(Duration::ZERO, |timer_callback| timer_callback.expired_time)
     }
 
     /// Return the remain time to expiration of this timer.
@@ -5,13 +5,17 @@
 pub use core::{timer, Clock};
 
 use ::core::time::Duration;
-pub use system_time::{SystemTime, START_TIME};
+pub use system_time::{
+    real_time_of, register_real_time_observer, set_real_time, unregister_real_time_observer,
+    SystemTime, START_TIME,
+};
 pub use timer::{Timer, TimerManager};
 
 use crate::prelude::*;
 
 pub mod clocks;
 mod core;
+pub mod namespace;
 mod softirq;
 mod system_time;
 pub mod timerfd;
@@ -0,0 +1,155 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Time namespaces.
+//!
+//! A time namespace virtualizes the monotonic clocks and the boot-time clock by adding offsets
+//! to them, so that a process restored from a checkpoint sees the clocks continue from where
+//! they were. The real-time clock is not virtualized.
+//!
+//! A new time namespace is created by `unshare(CLONE_NEWTIME)`, which only affects the children
+//! of the caller, or by `clone3(CLONE_NEWTIME)`. The offsets can be written in
+//! `/proc/[pid]/timens_offsets` until a process enters the namespace.
+
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use spin::Once;
+
+use super::{
+    clockid_t,
+    clocks::{BootTimeClock, MonotonicClock},
+    Clock, NSEC_PER_SEC,
+};
+use crate::{prelude::*, syscall::ClockId};
+
+/// A time namespace.
+pub struct TimeNamespace {
+    offsets: SpinLock<TimeNsOffsets>,
+    /// Whether a process has entered the namespace, after which the offsets are frozen.
+    is_entered: AtomicBool,
+}
+
+/// The clock offsets of a time namespace.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeNsOffsets {
+    /// The offset of the monotonic clocks in nanoseconds.
+    pub monotonic: i64,
+    /// The offset of the boot-time clock in nanoseconds.
+    pub boottime: i64,
+}
+
+static INIT_TIME_NS: Once<Arc<TimeNamespace>> = Once::new();
+
+impl TimeNamespace {
+    /// Returns the initial time namespace, whose offsets are zero.
+    pub fn get_init() -> &'static Arc<TimeNamespace> {
+        INIT_TIME_NS.call_once(|| {
+            let ns = Self::new(TimeNsOffsets::default());
+            ns.is_entered.store(true, Ordering::Relaxed);
+            ns
+        })
+    }
+
+    fn new(offsets: TimeNsOffsets) -> Arc<Self> {
+        Arc::new(Self {
+            offsets: SpinLock::new(offsets),
+            is_entered: AtomicBool::new(false),
+        })
+    }
+
+    /// Creates a new time namespace with the same offsets as this one.
+    pub fn new_child(&self) -> Arc<Self> {
+        Self::new(self.offsets())
+    }
+
+    /// Returns the clock offsets.
+    pub fn offsets(&self) -> TimeNsOffsets {
+        *self.offsets.lock()
+    }
+
+    /// Sets the offsets of clocks.
+    ///
+    /// Only the offsets of `CLOCK_MONOTONIC` and `CLOCK_BOOTTIME` can be set, and they can only
+    /// be set before any process enters the namespace. The offsets are set all or nothing.
+    pub fn set_offsets(&self, new_offsets: &[(clockid_t, i64)]) -> Result<()> {
+        for &(clockid, offset) in new_offsets {
+            let now = match ClockId::try_from(clockid) {
+                Ok(ClockId::CLOCK_MONOTONIC) => MonotonicClock::get().read_time(),
+                Ok(ClockId::CLOCK_BOOTTIME) => BootTimeClock::get().read_time(),
+                _ => return_errno_with_message!(Errno::EINVAL, "the clock cannot have an offset"),
+            };
+            // Like Linux, the clock in the namespace must not be negative or overflow.
+            let is_valid = (now.as_nanos() as i64)
+                .checked_add(offset)
+                .is_some_and(|time| (0..=MAX_NS_TIME).contains(&time));
+            if !is_valid {
+                return_errno_with_message!(Errno::ERANGE, "the offset is out of range");
+            }
+        }
+
+        let mut offsets = self.offsets.lock();
+        if self.is_entered.load(Ordering::Relaxed) {
+            return_errno_with_message!(
+                Errno::EACCES,
+                "the offsets cannot be changed after a process has entered the namespace"
+            );
+        }
+        for &(clockid, offset) in new_offsets {
+            if clockid == ClockId::CLOCK_MONOTONIC as clockid_t {
+                offsets.monotonic = offset;
+            } else {
+                offsets.boottime = offset;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Marks that a process has entered the namespace, which freezes the offsets.
+    pub fn mark_entered(&self) {
+        let _offsets = self.offsets.lock();
+        self.is_entered.store(true, Ordering::Relaxed);
+    }
+
+    /// Converts the time of a clock from the host to this namespace.
+    pub fn host_to_ns(&self, clockid: clockid_t, time: Duration) -> Duration {
+        apply_offset(time, self.offset_of(clockid))
+    }
+
+    /// Converts the time of a clock from this namespace to the host.
+    pub fn ns_to_host(&self, clockid: clockid_t, time: Duration) -> Duration {
+        apply_offset(time, self.offset_of(clockid).saturating_neg())
+    }
+
+    fn offset_of(&self, clockid: clockid_t) -> i64 {
+        let Ok(clock_id) = ClockId::try_from(clockid) else {
+            return 0;
+        };
+
+        match clock_id {
+            ClockId::CLOCK_MONOTONIC
+            | ClockId::CLOCK_MONOTONIC_RAW
+            | ClockId::CLOCK_MONOTONIC_COARSE => self.offsets.lock().monotonic,
+            ClockId::CLOCK_BOOTTIME => self.offsets.lock().boottime,
+            ClockId::CLOCK_REALTIME
+            | ClockId::CLOCK_REALTIME_COARSE
+            | ClockId::CLOCK_PROCESS_CPUTIME_ID
+            | ClockId::CLOCK_THREAD_CPUTIME_ID => 0,
+        }
+    }
+}
+
+/// The maximum time of a clock in a time namespace in nanoseconds.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/kernel/time/namespace.c#L421>
+const MAX_NS_TIME: i64 = (i64::MAX / NSEC_PER_SEC / 2) * NSEC_PER_SEC;
+
+fn apply_offset(time: Duration, offset: i64) -> Duration {
+    if offset >= 0 {
+        time.saturating_add(Duration::from_nanos(offset as u64))
+    } else {
+        time.saturating_sub(Duration::from_nanos(offset.unsigned_abs()))
+    }
+}
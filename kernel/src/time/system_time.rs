@@ -1,12 +1,18 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::time::Duration;
+use core::{
+    sync::atomic::{AtomicI64, Ordering},
+    time::Duration,
+};
 
 use aster_time::{read_monotonic_time, read_start_time};
 use spin::Once;
 use time::{Date, Month, PrimitiveDateTime, Time};
 
-use crate::prelude::*;
+use crate::{
+    events::{Observer, Subject},
+    prelude::*,
+};
 
 /// This struct corresponds to `SystemTime` in Rust std.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -15,6 +21,14 @@ pub struct SystemTime(PrimitiveDateTime);
 pub static START_TIME: Once<SystemTime> = Once::new();
 pub(super) static START_TIME_AS_DURATION: Once<Duration> = Once::new();
 
+/// The nanoseconds that the real time has been set forward (or backward, if negative).
+///
+/// The real time is the start time plus the monotonic time plus this offset.
+static REAL_TIME_OFFSET_NANOS: AtomicI64 = AtomicI64::new(0);
+
+/// The subject that notifies the observers when the real time is set.
+static REAL_TIME_SUBJECT: Subject<()> = Subject::new();
+
 pub(super) fn init() {
     let start_time = convert_system_time(read_start_time()).unwrap();
     START_TIME_AS_DURATION
@@ -40,12 +54,15 @@ impl SystemTime {
 
     /// Returns the current system time
     pub fn now() -> Self {
+        Self::from_monotonic(read_monotonic_time())
+    }
+
+    /// Returns the system time when the monotonic time is `monotonic_time`.
+    pub fn from_monotonic(monotonic_time: Duration) -> Self {
+        let offset = time::Duration::nanoseconds(REAL_TIME_OFFSET_NANOS.load(Ordering::Relaxed));
         // The get real time result should always be valid
-        START_TIME
-            .get()
-            .unwrap()
-            .checked_add(read_monotonic_time())
-            .unwrap()
+        let start_time = START_TIME.get().unwrap().0.checked_add(offset).unwrap();
+        SystemTime(start_time).checked_add(monotonic_time).unwrap()
     }
 
     /// Add a duration to self. If the result does not exceed inner bounds return Some(t), else return None.
@@ -81,6 +98,53 @@ impl SystemTime {
     }
 }
 
+/// Returns the real time (i.e., the duration since the Unix epoch) when the monotonic time is
+/// `monotonic_time`.
+pub fn real_time_of(monotonic_time: Duration) -> Duration {
+    SystemTime::from_monotonic(monotonic_time)
+        .duration_since(&SystemTime::UNIX_EPOCH)
+        .unwrap()
+}
+
+/// Sets the real time to `real_time`, which is the duration since the Unix epoch.
+///
+/// The monotonic time is not affected. So, like Linux, the real time cannot be set before the
+/// monotonic time, otherwise the offset between them would be negative.
+pub fn set_real_time(real_time: Duration) -> Result<()> {
+    let monotonic_time = read_monotonic_time();
+    if real_time < monotonic_time {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the real time cannot be set before the monotonic time"
+        );
+    }
+
+    let start_time = *START_TIME_AS_DURATION.get().unwrap();
+    let offset = real_time.as_nanos() as i128
+        - start_time.as_nanos() as i128
+        - monotonic_time.as_nanos() as i128;
+    let offset = i64::try_from(offset)
+        .map_err(|_| Error::with_message(Errno::EINVAL, "the real time is out of range"))?;
+    REAL_TIME_OFFSET_NANOS.store(offset, Ordering::Relaxed);
+
+    crate::vdso::update_vdso_real_time();
+    REAL_TIME_SUBJECT.notify_observers(&());
+
+    Ok(())
+}
+
+/// Registers an observer that is notified when the real time is set.
+///
+/// The observer is removed automatically once it is freed.
+pub fn register_real_time_observer(observer: Weak<dyn Observer<()>>) {
+    REAL_TIME_SUBJECT.register_observer(observer, ());
+}
+
+/// Unregisters an observer that is notified when the real time is set.
+pub fn unregister_real_time_observer(observer: &Weak<dyn Observer<()>>) {
+    REAL_TIME_SUBJECT.unregister_observer(observer);
+}
+
 /// convert ostd::time::Time to System time
 fn convert_system_time(system_time: aster_time::SystemTime) -> Result<SystemTime> {
     let month = match Month::try_from(system_time.month) {
@@ -1,10 +1,10 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::sync::atomic::{AtomicU64, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
 use super::clockid_t;
 use crate::{
-    events::IoEvents,
+    events::{IoEvents, Observer},
     fs::{
         file_handle::FileLike,
         utils::{CreationFlags, InodeMode, InodeType, Metadata, StatusFlags},
@@ -14,16 +14,33 @@ use crate::{
         signal::{PollHandle, Pollable, Pollee},
         Gid, Uid,
     },
-    syscall::create_timer,
-    time::{clocks::RealTimeClock, Timer},
+    syscall::{create_timer, ClockId},
+    time::{
+        clocks::RealTimeClock, register_real_time_observer, unregister_real_time_observer, Timer,
+    },
 };
 
 /// A file-like object representing a timer that can be used with file descriptors.
 pub struct TimerfdFile {
+    clockid: clockid_t,
     timer: Arc<Timer>,
     ticks: Arc<AtomicU64>,
     pollee: Pollee,
     flags: SpinLock<TFDFlags>,
+    cancel_observer: Arc<CancelObserver>,
+}
+
+/// An observer that cancels a timerfd when the real time is set.
+struct CancelObserver {
+    is_canceled: AtomicBool,
+    pollee: Pollee,
+}
+
+impl Observer<()> for CancelObserver {
+    fn on_events(&self, _events: &()) {
+        self.is_canceled.store(true, Ordering::Release);
+        self.pollee.notify(IoEvents::IN);
+    }
 }
 
 bitflags! {
@@ -51,11 +68,18 @@ impl TimerfdFile {
             create_timer(clockid, expired_fn, ctx)
         }?;
 
+        let cancel_observer = Arc::new(CancelObserver {
+            is_canceled: AtomicBool::new(false),
+            pollee: pollee.clone(),
+        });
+
         Ok(TimerfdFile {
+            clockid,
             timer,
             ticks,
             pollee,
             flags: SpinLock::new(flags),
+            cancel_observer,
         })
     }
 
@@ -64,16 +88,47 @@ impl TimerfdFile {
         &self.timer
     }
 
+    /// Gets the ID of the clock that the timer is based on.
+    pub fn clockid(&self) -> clockid_t {
+        self.clockid
+    }
+
     /// Clears the tick count.
     pub fn clear_ticks(&self) {
         self.ticks.store(0, Ordering::Release);
     }
 
+    /// Sets whether the timer is canceled when the real time is set.
+    ///
+    /// This takes effect only if the timer is based on the real-time clock. Once the timer is
+    /// canceled, the next read will fail with [`Errno::ECANCELED`].
+    pub fn set_cancel_on_set(&self, cancel_on_set: bool) {
+        self.cancel_observer
+            .is_canceled
+            .store(false, Ordering::Release);
+
+        let observer: Weak<dyn Observer<()>> = Arc::downgrade(&self.cancel_observer);
+        if cancel_on_set && self.clockid == ClockId::CLOCK_REALTIME as clockid_t {
+            register_real_time_observer(observer);
+        } else {
+            unregister_real_time_observer(&observer);
+        }
+    }
+
     fn is_nonblocking(&self) -> bool {
         self.flags.lock().contains(TFDFlags::TFD_NONBLOCK)
     }
 
     fn try_read(&self, writer: &mut VmWriter) -> Result<()> {
+        if self
+            .cancel_observer
+            .is_canceled
+            .swap(false, Ordering::AcqRel)
+        {
+            self.clear_ticks();
+            return_errno_with_message!(Errno::ECANCELED, "the real time has been set");
+        }
+
         let ticks = self.ticks.fetch_and(0, Ordering::AcqRel);
 
         if ticks == 0 {
@@ -88,7 +143,9 @@ impl TimerfdFile {
     fn check_io_events(&self) -> IoEvents {
         let mut events = IoEvents::empty();
 
-        if self.ticks.load(Ordering::Acquire) != 0 {
+        if self.ticks.load(Ordering::Acquire) != 0
+            || self.cancel_observer.is_canceled.load(Ordering::Acquire)
+        {
             events |= IoEvents::IN;
         }
 
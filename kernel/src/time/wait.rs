@@ -4,7 +4,11 @@ use core::time::Duration;
 
 use ostd::sync::{WaitQueue, Waiter};
 
-use super::{clocks::JIFFIES_TIMER_MANAGER, timer::Timeout, Timer, TimerManager};
+use super::{
+    clocks::{MonotonicClock, JIFFIES_TIMER_MANAGER},
+    timer::Timeout,
+    Timer, TimerManager,
+};
 use crate::prelude::*;
 
 /// A trait that provide the timeout related function for [`Waiter`] and [`WaitQueue`]`.
@@ -134,6 +138,17 @@ impl<'a> ManagedTimeout<'a> {
         Self::new_with_manager(timeout, manager)
     }
 
+    /// Creates a new high-resolution `ManagedTimeout`.
+    ///
+    /// Unlike [`Self::new`], whose expiration is measured in jiffies, the timeout is measured
+    /// against [`MonotonicClock`] with nanosecond precision. So a timeout shorter than a jiffy is
+    /// not rounded to whole jiffies.
+    pub fn new_high_res(timeout: Duration) -> Self {
+        let timeout = Timeout::After(timeout);
+        let manager = MonotonicClock::timer_manager();
+        Self::new_with_manager(timeout, manager)
+    }
+
     /// Creates a new `ManagedTimeout` with the given timer manager.
     pub const fn new_with_manager(timeout: Timeout, manager: &'a Arc<TimerManager>) -> Self {
         Self { timeout, manager }
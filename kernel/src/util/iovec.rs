@@ -1,9 +1,19 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::ops::Range;
+
 use ostd::mm::{Infallible, VmSpace};
 
 use crate::prelude::*;
 
+/// The maximum number of IO vectors in a system call.
+pub const IOV_MAX: usize = 1024;
+
+/// The maximum number of bytes that can be transferred by a system call.
+///
+/// Following Linux, the total length of the IO vectors is truncated to this limit.
+const MAX_RW_COUNT: usize = (i32::MAX as usize) & !(PAGE_SIZE - 1);
+
 /// A kernel space IO vector.
 #[derive(Debug, Clone, Copy)]
 struct IoVec {
@@ -54,17 +64,24 @@ impl IoVec {
 }
 
 /// The util function for create [`VmReader`]/[`VmWriter`]s.
+///
+/// This method fails with [`Errno::EINVAL`] if there are more than [`IOV_MAX`] IO vectors.
 fn copy_iovs_and_convert<'a, T: 'a>(
     user_space: &'a CurrentUserSpace<'a>,
     start_addr: Vaddr,
     count: usize,
     convert_iovec: impl Fn(&IoVec, &'a VmSpace) -> Result<T>,
 ) -> Result<Box<[T]>> {
+    if count > IOV_MAX {
+        return_errno_with_message!(Errno::EINVAL, "the number of IO vectors exceeds the limit");
+    }
+
     let vm_space = user_space.root_vmar().vm_space();
 
     let mut v = Vec::with_capacity(count);
+    let mut total_len = 0;
     for idx in 0..count {
-        let iov = {
+        let mut iov = {
             let addr = start_addr + idx * core::mem::size_of::<UserIoVec>();
             let uiov: UserIoVec = vm_space
                 .reader(addr, core::mem::size_of::<UserIoVec>())?
@@ -72,6 +89,9 @@ fn copy_iovs_and_convert<'a, T: 'a>(
             IoVec::try_from(uiov)?
         };
 
+        iov.len = iov.len.min(MAX_RW_COUNT - total_len);
+        total_len += iov.len;
+
         if iov.is_empty() {
             continue;
         }
@@ -83,6 +103,22 @@ fn copy_iovs_and_convert<'a, T: 'a>(
     Ok(v.into_boxed_slice())
 }
 
+/// Copies the IO vectors from the user space, and returns the user buffers that they point to.
+///
+/// Unlike [`VmReaderArray`] and [`VmWriterArray`], the returned buffers do not borrow the user
+/// space, so they can be accessed later, e.g., by kernel threads.
+pub fn copy_io_vec_ranges_from_user(
+    user_space: &CurrentUserSpace,
+    start_addr: Vaddr,
+    count: usize,
+) -> Result<Box<[Range<Vaddr>]>> {
+    copy_iovs_and_convert(user_space, start_addr, count, |iov, vm_space| {
+        // Check that the buffer is in the user space.
+        vm_space.writer(iov.base, iov.len)?;
+        Ok(iov.base..iov.base + iov.len)
+    })
+}
+
 /// A collection of [`VmReader`]s.
 ///
 /// Such readers are built from user-provided buffer, so it's always fallible.
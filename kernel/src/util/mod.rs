@@ -5,4 +5,6 @@ pub mod net;
 pub mod random;
 pub mod ring_buffer;
 
-pub use iovec::{MultiRead, MultiWrite, VmReaderArray, VmWriterArray};
+pub use iovec::{
+    copy_io_vec_ranges_from_user, MultiRead, MultiWrite, VmReaderArray, VmWriterArray, IOV_MAX,
+};
@@ -245,45 +245,30 @@ pub fn write_socket_addr_with_max_len(
         );
     }
 
-    let actual_len = match socket_addr {
-        SocketAddr::IPv4(addr, port) => write_c_socket_address_util::<CSocketAddrInet, _>(
-            (*addr, *port),
-            dest,
-            max_len as usize,
-        )?,
-        SocketAddr::Unix(addr) => unix::into_c_bytes_and(addr, |bytes| {
-            let written_len = min(bytes.len(), max_len as _);
-            current_userspace!().write_bytes(dest, &mut VmReader::from(&bytes[..written_len]))?;
-            Ok::<usize, Error>(bytes.len())
-        })?,
-        SocketAddr::Netlink(addr) => {
-            write_c_socket_address_util::<CSocketAddrNetlink, _>(*addr, dest, max_len as usize)?
-        }
-        SocketAddr::Vsock(addr) => {
-            write_c_socket_address_util::<CSocketAddrVm, _>(*addr, dest, max_len as usize)?
-        }
-    };
+    let actual_len = socket_addr_to_c_bytes_and(socket_addr, |bytes| {
+        let written_len = min(bytes.len(), max_len as _);
+        current_userspace!().write_bytes(dest, &mut VmReader::from(&bytes[..written_len]))?;
+        Ok::<usize, Error>(bytes.len())
+    })?;
 
     Ok(actual_len as i32)
 }
 
-// Utility function to write a C socket address to user space.
-fn write_c_socket_address_util<TCSockAddr: Pod, TSockAddr>(
-    addr: TSockAddr,
-    dest: Vaddr,
-    max_len: usize,
-) -> Result<usize>
+/// Converts a socket address to the bytes of the corresponding C structure, and calls `f` with
+/// the bytes.
+///
+/// # Panics
+///
+/// This method will panic if the socket address cannot be validly mapped to the corresponding
+/// Linux C structures. See [`write_socket_addr_with_max_len`] for details.
+pub fn socket_addr_to_c_bytes_and<R, F>(socket_addr: &SocketAddr, f: F) -> R
 where
-    TCSockAddr: From<TSockAddr>,
+    F: FnOnce(&[u8]) -> R,
 {
-    let c_socket_addr = TCSockAddr::from(addr);
-    let actual_len = size_of::<TCSockAddr>();
-    let written_len = min(actual_len, max_len);
-
-    current_userspace!().write_bytes(
-        dest,
-        &mut VmReader::from(&c_socket_addr.as_bytes()[..written_len]),
-    )?;
-
-    Ok(actual_len)
+    match socket_addr {
+        SocketAddr::IPv4(addr, port) => f(CSocketAddrInet::from((*addr, *port)).as_bytes()),
+        SocketAddr::Unix(addr) => unix::into_c_bytes_and(addr, f),
+        SocketAddr::Netlink(addr) => f(CSocketAddrNetlink::from(*addr).as_bytes()),
+        SocketAddr::Vsock(addr) => f(CSocketAddrVm::from(*addr).as_bytes()),
+    }
 }
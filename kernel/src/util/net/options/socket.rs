@@ -2,9 +2,10 @@
 
 use super::RawSocketOption;
 use crate::{
-    impl_raw_sock_option_get_only, impl_raw_socket_option,
+    impl_raw_sock_option_get_only, impl_raw_sock_option_set_only, impl_raw_socket_option,
     net::socket::options::{
-        Error, KeepAlive, Linger, RecvBuf, ReuseAddr, ReusePort, SendBuf, SocketOption,
+        AttachBpf, BusyPoll, DetachBpf, Error, KeepAlive, Linger, OobInline, PassCred, PeerCred,
+        RecvBuf, ReuseAddr, ReusePort, SendBuf, SocketOption, Timestamp, TimestampNs,
     },
     prelude::*,
 };
@@ -34,6 +35,13 @@ enum CSocketOptionName {
     LINGER = 13,
     BSDCOMPAT = 14,
     REUSEPORT = 15,
+    PASSCRED = 16,
+    PEERCRED = 17,
+    DETACH_FILTER = 27,
+    TIMESTAMP_OLD = 29,
+    TIMESTAMPNS_OLD = 35,
+    BUSY_POLL = 46,
+    ATTACH_BPF = 50,
     RCVTIMEO_NEW = 66,
     SNDTIMEO_NEW = 67,
 }
@@ -48,6 +56,14 @@ pub fn new_socket_option(name: i32) -> Result<Box<dyn RawSocketOption>> {
         CSocketOptionName::REUSEPORT => Ok(Box::new(ReusePort::new())),
         CSocketOptionName::LINGER => Ok(Box::new(Linger::new())),
         CSocketOptionName::KEEPALIVE => Ok(Box::new(KeepAlive::new())),
+        CSocketOptionName::OOBINLINE => Ok(Box::new(OobInline::new())),
+        CSocketOptionName::PASSCRED => Ok(Box::new(PassCred::new())),
+        CSocketOptionName::PEERCRED => Ok(Box::new(PeerCred::new())),
+        CSocketOptionName::TIMESTAMP_OLD => Ok(Box::new(Timestamp::new())),
+        CSocketOptionName::TIMESTAMPNS_OLD => Ok(Box::new(TimestampNs::new())),
+        CSocketOptionName::BUSY_POLL => Ok(Box::new(BusyPoll::new())),
+        CSocketOptionName::ATTACH_BPF => Ok(Box::new(AttachBpf::new())),
+        CSocketOptionName::DETACH_FILTER => Ok(Box::new(DetachBpf::new())),
         _ => return_errno_with_message!(Errno::ENOPROTOOPT, "unsupported socket-level option"),
     }
 }
@@ -59,3 +75,11 @@ impl_raw_sock_option_get_only!(Error);
 impl_raw_socket_option!(ReusePort);
 impl_raw_socket_option!(Linger);
 impl_raw_socket_option!(KeepAlive);
+impl_raw_socket_option!(OobInline);
+impl_raw_socket_option!(PassCred);
+impl_raw_sock_option_get_only!(PeerCred);
+impl_raw_socket_option!(Timestamp);
+impl_raw_socket_option!(TimestampNs);
+impl_raw_socket_option!(BusyPoll);
+impl_raw_sock_option_set_only!(AttachBpf);
+impl_raw_sock_option_set_only!(DetachBpf);
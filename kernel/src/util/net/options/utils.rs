@@ -3,9 +3,12 @@
 use core::{num::NonZeroU8, time::Duration};
 
 use crate::{
+    bpf::SocketFilter,
     current_userspace,
+    fs::file_table::FileDesc,
     net::socket::{
         ip::{options::IpTtl, stream::CongestionControl},
+        unix::UnixCredentials,
         LingerOption,
     },
     prelude::*,
@@ -136,6 +139,17 @@ impl WriteToUser for Option<Error> {
     }
 }
 
+impl WriteToUser for UnixCredentials {
+    fn write_to_user(&self, addr: Vaddr, max_len: u32) -> Result<usize> {
+        // Like Linux, the credentials are truncated if the buffer is too short.
+        let write_len = size_of::<UnixCredentials>().min(max_len as usize);
+
+        current_userspace!()
+            .write_bytes(addr, &mut VmReader::from(&self.as_bytes()[..write_len]))?;
+        Ok(write_len)
+    }
+}
+
 impl ReadFromUser for LingerOption {
     fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
         if (max_len as usize) < core::mem::size_of::<CLinger>() {
@@ -162,6 +176,21 @@ impl WriteToUser for LingerOption {
     }
 }
 
+impl ReadFromUser for () {
+    fn read_from_user(_addr: Vaddr, _max_len: u32) -> Result<Self> {
+        Ok(())
+    }
+}
+
+impl ReadFromUser for Arc<SocketFilter> {
+    fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
+        let fd = FileDesc::read_from_user(addr, max_len)?;
+        let filter = SocketFilter::from_fd(fd)?;
+
+        Ok(Arc::new(filter))
+    }
+}
+
 const TCP_CONGESTION_NAME_MAX: u32 = 16;
 
 impl ReadFromUser for CongestionControl {
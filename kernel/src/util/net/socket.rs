@@ -2,9 +2,9 @@
 
 use super::read_socket_addr_from_user;
 use crate::{
-    net::socket::SocketAddr,
+    net::socket::{ControlMessage, SendRecvFlags, SocketAddr},
     prelude::*,
-    util::{net::write_socket_addr_with_max_len, VmReaderArray, VmWriterArray},
+    util::{net::write_socket_addr_with_max_len, VmReaderArray, VmWriterArray, IOV_MAX},
 };
 
 /// Standard well-defined IP protocols.
@@ -65,15 +65,6 @@ pub enum SockType {
 
 pub const SOCK_TYPE_MASK: i32 = 0xf;
 
-bitflags! {
-    #[repr(C)]
-    #[derive(Pod)]
-    pub struct SockFlags: i32 {
-        const SOCK_NONBLOCK = 1 << 11;
-        const SOCK_CLOEXEC = 1 << 19;
-    }
-}
-
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pod)]
 pub struct CUserMsgHdr {
@@ -84,11 +75,11 @@ pub struct CUserMsgHdr {
     /// Scatter/Gather iov array
     pub msg_iov: Vaddr,
     /// The # of elements in msg_iov
-    pub msg_iovlen: u32,
+    pub msg_iovlen: usize,
     /// Ancillary data
     pub msg_control: Vaddr,
     /// Ancillary data buffer length
-    pub msg_controllen: u32,
+    pub msg_controllen: usize,
     /// Flags on received message
     pub msg_flags: u32,
 }
@@ -103,12 +94,51 @@ impl CUserMsgHdr {
         Ok(Some(socket_addr))
     }
 
-    pub fn write_socket_addr_to_user(&self, addr: &SocketAddr) -> Result<()> {
+    /// Writes the socket address to the user space and updates `msg_namelen` accordingly.
+    pub fn write_socket_addr_to_user(&mut self, addr: Option<&SocketAddr>) -> Result<()> {
         if self.msg_name == 0 {
             return Ok(());
         }
 
-        write_socket_addr_with_max_len(addr, self.msg_name, self.msg_namelen)?;
+        self.msg_namelen = match addr {
+            Some(addr) => write_socket_addr_with_max_len(addr, self.msg_name, self.msg_namelen)?,
+            None => 0,
+        };
+        Ok(())
+    }
+
+    pub fn read_control_messages_from_user(&self, ctx: &Context) -> Result<Vec<ControlMessage>> {
+        if self.msg_control == 0 {
+            return Ok(Vec::new());
+        }
+
+        ControlMessage::read_all_from_user(self.msg_control, self.msg_controllen, ctx)
+    }
+
+    /// Writes the control messages to the user space and updates `msg_controllen` and
+    /// `msg_flags` accordingly.
+    pub fn write_control_messages_to_user(
+        &mut self,
+        control_messages: Vec<ControlMessage>,
+        is_cloexec: bool,
+        ctx: &Context,
+    ) -> Result<()> {
+        let (written_len, is_truncated) = if self.msg_control == 0 {
+            (0, !control_messages.is_empty())
+        } else {
+            ControlMessage::write_all_to_user(
+                control_messages,
+                self.msg_control,
+                self.msg_controllen,
+                is_cloexec,
+                ctx,
+            )?
+        };
+
+        self.msg_controllen = written_len;
+        if is_truncated {
+            self.msg_flags |= SendRecvFlags::MSG_CTRUNC.bits() as u32;
+        }
         Ok(())
     }
 
@@ -116,13 +146,25 @@ impl CUserMsgHdr {
         &self,
         user_space: &'a CurrentUserSpace<'a>,
     ) -> Result<VmReaderArray<'a>> {
-        VmReaderArray::from_user_io_vecs(user_space, self.msg_iov, self.msg_iovlen as usize)
+        self.check_iovlen()?;
+        VmReaderArray::from_user_io_vecs(user_space, self.msg_iov, self.msg_iovlen)
     }
 
     pub fn copy_writer_array_from_user<'a>(
         &self,
         user_space: &'a CurrentUserSpace<'a>,
     ) -> Result<VmWriterArray<'a>> {
-        VmWriterArray::from_user_io_vecs(user_space, self.msg_iov, self.msg_iovlen as usize)
+        self.check_iovlen()?;
+        VmWriterArray::from_user_io_vecs(user_space, self.msg_iov, self.msg_iovlen)
+    }
+
+    fn check_iovlen(&self) -> Result<()> {
+        if self.msg_iovlen > IOV_MAX {
+            return_errno_with_message!(
+                Errno::EMSGSIZE,
+                "the number of IO vectors exceeds the limit"
+            );
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The continuous health tests of the entropy sources.
+//!
+//! The tests are the repetition count test and the adaptive proportion test of NIST SP 800-90B
+//! (Section 4.4), which detect a source that gets stuck or loses much of its entropy. The cutoffs
+//! are derived from the assumed min-entropy per sample with the false positive probability of
+//! 2^-30.
+
+use crate::prelude::*;
+
+/// The number of the samples in a window of the adaptive proportion test.
+const APT_WINDOW_SIZE: usize = 512;
+
+/// The number of the samples that must pass the tests before a source is used.
+pub(super) const NR_STARTUP_SAMPLES: usize = 1024;
+
+/// The health tests of an entropy source.
+#[derive(Debug)]
+pub(super) struct HealthTests {
+    rct_cutoff: usize,
+    apt_cutoff: usize,
+    /// The last sample and the number of its consecutive repetitions
+    rct_state: Option<(u64, usize)>,
+    /// The first sample of the window, the number of its occurrences, and the number of the
+    /// samples in the window
+    apt_state: Option<(u64, usize, usize)>,
+}
+
+/// The failures of the health tests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum HealthFailure {
+    /// A sample is repeated too many times in a row.
+    RepetitionCount,
+    /// A sample occurs too many times in a window.
+    AdaptiveProportion,
+}
+
+impl From<HealthFailure> for Error {
+    fn from(failure: HealthFailure) -> Self {
+        match failure {
+            HealthFailure::RepetitionCount => {
+                Error::with_message(Errno::EIO, "the repetition count test fails")
+            }
+            HealthFailure::AdaptiveProportion => {
+                Error::with_message(Errno::EIO, "the adaptive proportion test fails")
+            }
+        }
+    }
+}
+
+impl HealthTests {
+    /// Creates the health tests of a source whose samples have the min-entropy.
+    ///
+    /// The min-entropy is given in the 1/4 bits, so that the jitter, whose samples have less
+    /// than a bit of entropy, can be tested.
+    pub(super) fn new(quarter_bits_per_sample: usize) -> Self {
+        let (rct_cutoff, apt_cutoff) = match quarter_bits_per_sample {
+            0 => panic!("the source has no entropy"),
+            // The cutoffs of a lower min-entropy are used if there are no exact ones.
+            1 => (121, 477),
+            2..=3 => (61, 422),
+            4..=31 => (31, 325),
+            32..=63 => (5, 17),
+            64..=127 => (3, 5),
+            _ => (2, 3),
+        };
+        Self {
+            rct_cutoff,
+            apt_cutoff,
+            rct_state: None,
+            apt_state: None,
+        }
+    }
+
+    /// Tests the next sample.
+    pub(super) fn test(&mut self, sample: u64) -> core::result::Result<(), HealthFailure> {
+        let rct_count = match self.rct_state {
+            Some((last, count)) if last == sample => count + 1,
+            _ => 1,
+        };
+        self.rct_state = Some((sample, rct_count));
+        if rct_count >= self.rct_cutoff {
+            return Err(HealthFailure::RepetitionCount);
+        }
+
+        let (first, apt_count, window_len) = match self.apt_state {
+            Some((first, count, len)) if len < APT_WINDOW_SIZE => {
+                (first, count + usize::from(first == sample), len + 1)
+            }
+            _ => (sample, 1, 1),
+        };
+        self.apt_state = Some((first, apt_count, window_len));
+        if apt_count >= self.apt_cutoff {
+            return Err(HealthFailure::AdaptiveProportion);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn stuck_source() {
+        let mut tests = HealthTests::new(256);
+        assert_eq!(tests.test(u64::MAX), Ok(()));
+        assert_eq!(tests.test(u64::MAX), Err(HealthFailure::RepetitionCount));
+
+        let mut tests = HealthTests::new(2);
+        for _ in 1..60 {
+            assert_eq!(tests.test(0), Ok(()));
+        }
+        assert_eq!(tests.test(0), Ok(()));
+        assert_eq!(tests.test(0), Err(HealthFailure::RepetitionCount));
+    }
+
+    #[ktest]
+    fn biased_source() {
+        // The first sample occurs in every other sample of the window.
+        let mut tests = HealthTests::new(32);
+        let mut result = Ok(());
+        for i in 0..APT_WINDOW_SIZE as u64 {
+            result = tests.test(if i % 2 == 0 { 0 } else { i });
+            if result.is_err() {
+                break;
+            }
+        }
+        assert_eq!(result, Err(HealthFailure::AdaptiveProportion));
+    }
+
+    #[ktest]
+    fn healthy_source() {
+        let mut tests = HealthTests::new(256);
+        let mut sample = 0x243f_6a88_85a3_08d3u64;
+        for _ in 0..NR_STARTUP_SAMPLES {
+            // A xorshift generator, which never repeats a sample in the short run.
+            sample ^= sample << 13;
+            sample ^= sample >> 7;
+            sample ^= sample << 17;
+            assert_eq!(tests.test(sample), Ok(()));
+        }
+    }
+}
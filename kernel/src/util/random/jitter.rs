@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The entropy collected from the CPU execution time jitter.
+//!
+//! The time to execute the same memory accesses varies because of the caches, the TLBs, the
+//! pipelines, and the interrupts, like the jitter entropy source of Linux. The variations are
+//! measured with the TSC (or the time CSR on RISC-V), which needs no devices, so the entropy is
+//! available at boot time on all the platforms.
+
+use ostd::arch::read_tsc;
+
+use super::health::{HealthFailure, HealthTests, NR_STARTUP_SAMPLES};
+use crate::{crypto::Sha256, prelude::*};
+
+/// The size of the memory that is accessed, which is larger than the L1 cache.
+const MEMORY_SIZE: usize = 64 * 1024;
+/// The distance between two accessed bytes, which is larger than a cache line.
+const ACCESS_STRIDE: usize = 67;
+/// The number of the memory accesses in a sample.
+const NR_ACCESSES_PER_SAMPLE: usize = 128;
+
+/// The assumed min-entropy of a sample in the 1/4 bits, i.e., half a bit.
+const QUARTER_BITS_PER_SAMPLE: usize = 2;
+/// The maximum number of the consecutive samples that are stuck.
+const MAX_STUCK_SAMPLES: usize = 1024;
+
+/// The collector of the jitter entropy.
+pub(super) struct JitterCollector {
+    memory: Vec<u8>,
+    position: usize,
+    health_tests: HealthTests,
+    /// The last time delta and the last difference between the time deltas
+    last_deltas: (u64, u64),
+}
+
+impl JitterCollector {
+    /// Creates a collector, which passes the startup health tests.
+    pub(super) fn new() -> Result<Self> {
+        let mut collector = Self {
+            memory: vec![0; MEMORY_SIZE],
+            position: 0,
+            health_tests: HealthTests::new(QUARTER_BITS_PER_SAMPLE),
+            last_deltas: (0, 0),
+        };
+        for _ in 0..NR_STARTUP_SAMPLES {
+            collector.next_sample()?;
+        }
+        Ok(collector)
+    }
+
+    /// Collects the entropy, which is condensed into a digest with full entropy.
+    pub(super) fn collect(&mut self) -> Result<[u8; Sha256::DIGEST_SIZE]> {
+        let nr_samples = Sha256::DIGEST_SIZE * 8 * 4 / QUARTER_BITS_PER_SAMPLE;
+
+        let mut hasher = Sha256::new();
+        let mut nr_good_samples = 0;
+        let mut nr_stuck_samples = 0;
+        while nr_good_samples < nr_samples {
+            let (delta, is_stuck) = self.next_sample()?;
+            // The stuck samples are mixed in but not credited.
+            hasher.update(&delta.to_ne_bytes());
+            if !is_stuck {
+                nr_good_samples += 1;
+                nr_stuck_samples = 0;
+                continue;
+            }
+            nr_stuck_samples += 1;
+            if nr_stuck_samples > MAX_STUCK_SAMPLES {
+                return_errno_with_message!(Errno::EIO, "the jitter entropy is stuck");
+            }
+        }
+
+        Ok(hasher.finish())
+    }
+
+    /// Measures the time of the memory accesses, and returns the time delta and whether the
+    /// sample is stuck.
+    ///
+    /// A sample is stuck if the time delta, or its first or second difference from the last ones,
+    /// is zero, which indicates that the timer is too coarse to measure the variations.
+    fn next_sample(&mut self) -> core::result::Result<(u64, bool), HealthFailure> {
+        let start = read_tsc();
+        for _ in 0..NR_ACCESSES_PER_SAMPLE {
+            let byte = &mut self.memory[self.position];
+            *byte = core::hint::black_box(byte.wrapping_add(1));
+            self.position = (self.position + ACCESS_STRIDE) % MEMORY_SIZE;
+        }
+        let delta = read_tsc().wrapping_sub(start);
+
+        let (last_delta, last_delta2) = self.last_deltas;
+        let delta2 = delta.wrapping_sub(last_delta);
+        let delta3 = delta2.wrapping_sub(last_delta2);
+        self.last_deltas = (delta, delta2);
+
+        self.health_tests.test(delta)?;
+        Ok((delta, delta == 0 || delta2 == 0 || delta3 == 0))
+    }
+}
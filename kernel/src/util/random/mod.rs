@@ -0,0 +1,149 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The random number generator (RNG) of the kernel.
+
+#![expect(unused_variables)]
+
+mod health;
+mod jitter;
+
+use ostd::arch::{read_random, read_random_seed};
+use rand::{rngs::StdRng, Error as RandError, RngCore, SeedableRng};
+use spin::Once;
+
+use self::{
+    health::{HealthTests, NR_STARTUP_SAMPLES},
+    jitter::JitterCollector,
+};
+use crate::{crypto::Sha256, prelude::*};
+
+static RNG: Once<SpinLock<StdRng>> = Once::new();
+
+/// Fill `dest` with random bytes.
+///
+/// It's cryptographically secure, as documented in [`rand::rngs::StdRng`].
+pub fn getrandom(dst: &mut [u8]) -> Result<()> {
+    Ok(RNG.get().unwrap().lock().try_fill_bytes(dst)?)
+}
+
+/// Fills `writer` with random bytes, returning the number of bytes written.
+///
+/// The bytes are generated page by page, so a large buffer (e.g., a user buffer whose length is
+/// controlled by the user) does not require a kernel buffer of the same size. If the writing
+/// fails after some bytes have been written, the number of the written bytes is returned.
+pub fn getrandom_fallible(writer: &mut VmWriter) -> Result<usize> {
+    let mut buf = vec![0u8; writer.avail().min(PAGE_SIZE)];
+
+    let mut written_len = 0;
+    while writer.has_avail() {
+        let chunk = &mut buf[..writer.avail().min(PAGE_SIZE)];
+        getrandom(chunk)?;
+        match writer.write_fallible(&mut VmReader::from(&*chunk)) {
+            Ok(len) => written_len += len,
+            Err((err, len)) if written_len + len == 0 => return Err(err.into()),
+            Err((_, len)) => return Ok(written_len + len),
+        }
+    }
+
+    Ok(written_len)
+}
+
+/// Seeds the RNG with the entropy sources that are available at boot time.
+///
+/// The RNG is seeded before the devices are probed, so it cannot depend on the interrupts of the
+/// devices. The entropy is collected from the following sources, and is mixed into the seed:
+///  - the hardware entropy sources (i.e., `rdseed` and `rdrand` on x86-64), whose outputs pass
+///    the health tests;
+///  - the seed in the device tree (i.e., `rng-seed` in `/chosen`) on RISC-V;
+///  - the jitter of the CPU execution time.
+///
+/// The sources are logged, and the kernel panics if no source is available.
+pub fn init() {
+    let mut hasher = Sha256::new();
+    let mut sources = Vec::new();
+
+    for (name, read) in [
+        ("rdseed", read_random_seed as fn() -> Option<u64>),
+        ("rdrand", read_random),
+    ] {
+        match collect_hardware_entropy(read) {
+            Ok(Some(entropy)) => {
+                hasher.update(&entropy);
+                sources.push(name);
+            }
+            Ok(None) => (),
+            Err(err) => warn!("the entropy source `{}` is unhealthy: {:?}", name, err),
+        }
+    }
+
+    #[cfg(target_arch = "riscv64")]
+    {
+        use ostd::arch::boot::DEVICE_TREE;
+
+        let chosen = DEVICE_TREE.get().unwrap().find_node("/chosen").unwrap();
+        if let Some(seed) = chosen.property("rng-seed") {
+            hasher.update(seed.value);
+            sources.push("rng-seed");
+        }
+    }
+
+    match JitterCollector::new().and_then(|mut collector| collector.collect()) {
+        Ok(entropy) => {
+            hasher.update(&entropy);
+            sources.push("jitter");
+        }
+        Err(err) => warn!("the entropy source `jitter` is unhealthy: {:?}", err),
+    }
+
+    if sources.is_empty() {
+        panic!("no entropy source is available to seed the RNG");
+    }
+    info!("The RNG is seeded from: {}", sources.join(", "));
+
+    let seed = hasher.finish();
+    RNG.call_once(|| SpinLock::new(StdRng::from_seed(seed)));
+}
+
+/// Collects the entropy from a hardware entropy source.
+///
+/// Returns `None` if the source is not available.
+fn collect_hardware_entropy(
+    read: fn() -> Option<u64>,
+) -> Result<Option<[u8; Sha256::DIGEST_SIZE]>> {
+    // The outputs are assumed to have half of the full entropy, which is conservative.
+    const QUARTER_BITS_PER_SAMPLE: usize = 128;
+    const NR_SAMPLES: usize = Sha256::DIGEST_SIZE * 8 * 4 / QUARTER_BITS_PER_SAMPLE;
+
+    let mut health_tests = HealthTests::new(QUARTER_BITS_PER_SAMPLE);
+    let mut read_sample = || -> Result<Option<u64>> {
+        let Some(sample) = read() else {
+            return Ok(None);
+        };
+        health_tests.test(sample)?;
+        Ok(Some(sample))
+    };
+
+    if read_sample()?.is_none() {
+        return Ok(None);
+    }
+    for _ in 1..NR_STARTUP_SAMPLES {
+        if read_sample()?.is_none() {
+            return_errno_with_message!(Errno::EIO, "the entropy source is exhausted");
+        }
+    }
+
+    let mut hasher = Sha256::new();
+    for _ in 0..NR_SAMPLES {
+        let Some(sample) = read_sample()? else {
+            return_errno_with_message!(Errno::EIO, "the entropy source is exhausted");
+        };
+        hasher.update(&sample.to_ne_bytes());
+    }
+    Ok(Some(hasher.finish()))
+}
+
+impl From<RandError> for Error {
+    fn from(value: RandError) -> Self {
+        Error::with_message(Errno::ENOSYS, "cannot generate random bytes")
+    }
+}
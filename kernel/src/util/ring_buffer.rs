@@ -308,12 +308,23 @@ impl<R: Deref<Target = RingBuffer<u8>>> Producer<u8, R> {
     ///
     /// Returns the number of bytes written.
     pub fn write_fallible(&mut self, reader: &mut dyn MultiRead) -> Result<usize> {
+        self.write_fallible_with_max_len(reader, usize::MAX)
+    }
+
+    /// Writes at most `max_len` bytes from the `VmReader` to the `RingBuffer`.
+    ///
+    /// Returns the number of bytes written.
+    pub fn write_fallible_with_max_len(
+        &mut self,
+        reader: &mut dyn MultiRead,
+        max_len: usize,
+    ) -> Result<usize> {
         let rb = &self.rb;
         let free_len = rb.free_len();
         if free_len == 0 {
             return Ok(0);
         }
-        let write_len = reader.sum_lens().min(free_len);
+        let write_len = reader.sum_lens().min(free_len).min(max_len);
 
         let tail = rb.tail();
         let write_len = if tail + write_len > rb.capacity {
@@ -401,6 +412,20 @@ impl<T: Pod, R: Deref<Target = RingBuffer<T>>> Consumer<T, R> {
         rb.advance_head(head, nitems);
         Some(())
     }
+
+    /// Skips a number of items in the `RingBuffer`.
+    ///
+    /// Returns `Some` on success, all the items are discarded from the ring buffer.
+    /// Returns `None` if the ring buffer does not have enough items.
+    pub fn skip(&mut self, nitems: usize) -> Option<()> {
+        let rb = &self.rb;
+        if nitems > rb.len() {
+            return None;
+        }
+
+        rb.advance_head(rb.head(), nitems);
+        Some(())
+    }
 }
 
 impl<R: Deref<Target = RingBuffer<u8>>> Consumer<u8, R> {
@@ -408,12 +433,23 @@ impl<R: Deref<Target = RingBuffer<u8>>> Consumer<u8, R> {
     ///
     /// Returns the number of bytes read.
     pub fn read_fallible(&mut self, writer: &mut dyn MultiWrite) -> Result<usize> {
+        self.read_fallible_with_max_len(writer, usize::MAX)
+    }
+
+    /// Reads at most `max_len` bytes from the `RingBuffer` to the `VmWriter`.
+    ///
+    /// Returns the number of bytes read.
+    pub fn read_fallible_with_max_len(
+        &mut self,
+        writer: &mut dyn MultiWrite,
+        max_len: usize,
+    ) -> Result<usize> {
         let rb = &self.rb;
         let len = rb.len();
         if len == 0 {
             return Ok(0);
         }
-        let read_len = writer.sum_lens().min(len);
+        let read_len = writer.sum_lens().min(len).min(max_len);
 
         let head = rb.head();
         let read_len = if head + read_len > rb.capacity {
@@ -0,0 +1,427 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Huge pages for hugetlbfs, `MAP_HUGETLB` and `SHM_HUGETLB`.
+//!
+//! Like Linux, the huge pages are taken from a pool of each huge page size, whose size is set by
+//! the administrator with `/proc/sys/vm/nr_hugepages` (for the default size) or
+//! `/sys/kernel/mm/hugepages/hugepages-<size>kB/nr_hugepages`. The memory that is backed by huge
+//! pages never exceeds the pool, so the applications (e.g., the databases that allocate their
+//! buffer pools from huge pages) fail at the allocation instead of at a page fault.
+//!
+//! A hugetlbfs mount may reserve huge pages from the pool with the `min_size=` option. The
+//! reserved pages are only used by the files of the mount, and are reported as `HugePages_Rsvd`
+//! in `/proc/meminfo`.
+//!
+//! Each huge page is allocated as physically contiguous frames when a file grows or a segment is
+//! created, and is returned to the pool when all of its frames are freed. The pool does not hold
+//! the free pages, so the allocation may still fail if the memory is used up or fragmented. The
+//! huge pages are mapped with base pages, since OSTD does not map huge frames to the user space
+//! yet.
+//!
+//! Reference: <https://docs.kernel.org/admin-guide/mm/hugetlbpage.html>
+
+use alloc::{borrow::Cow, format};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use align_ext::AlignExt;
+use aster_rights::Rights;
+use aster_systree::{
+    Error as SysTreeError, Result as SysTreeResult, SysAttrFlags, SysAttrSet, SysAttrSetBuilder,
+    SysNode, SysNodeId, SysNodeType, SysNormalNodeFields, SysObj, SysStr,
+};
+use ostd::{
+    impl_untyped_frame_meta_for,
+    mm::{FrameAllocOptions, UFrame, USegment},
+};
+
+use super::vmo::{Vmo, VmoOptions};
+use crate::prelude::*;
+
+/// The shift of the huge page size in the flags of `mmap` and `shmget`.
+pub const HUGETLB_FLAG_ENCODE_SHIFT: u32 = 26;
+/// The mask of the huge page size in the flags of `mmap` and `shmget` after shifting.
+pub const HUGETLB_FLAG_ENCODE_MASK: u32 = 0x3f;
+
+static POOLS: [HugePagePool; 2] = [
+    HugePagePool::new(2 << 20, "hugepages-2048kB"),
+    HugePagePool::new(1 << 30, "hugepages-1048576kB"),
+];
+
+/// A pool of the huge pages of a size.
+#[derive(Debug)]
+pub struct HugePagePool {
+    page_size: usize,
+    /// The name of the directory in `/sys/kernel/mm/hugepages`.
+    sys_name: &'static str,
+    counters: SpinLock<PoolCounters>,
+}
+
+#[derive(Debug)]
+struct PoolCounters {
+    /// The number of the huge pages in the pool (`nr_hugepages`).
+    nr_pages: usize,
+    /// The number of the frames of the huge pages that are in use.
+    nr_used_frames: usize,
+    /// The number of the huge pages that are reserved by the hugetlbfs mounts and not in use
+    /// (`resv_hugepages`).
+    nr_reserved: usize,
+}
+
+/// The huge pages used by a hugetlbfs mount.
+///
+/// The mount can use at most `size=` bytes of huge pages, and the huge pages below
+/// `min_size=` bytes are reserved from the pool.
+#[derive(Debug)]
+pub struct HugePageSubpool {
+    pool: &'static HugePagePool,
+    max_pages: Option<usize>,
+    min_pages: usize,
+    /// The number of the frames of the huge pages that are in use.
+    ///
+    /// This is only changed with the counters of the pool locked.
+    nr_used_frames: AtomicUsize,
+}
+
+/// The metadata of the frames of a huge page.
+#[derive(Debug)]
+struct HugePageFrameMeta {
+    pool: &'static HugePagePool,
+    subpool: Option<Arc<HugePageSubpool>>,
+}
+
+impl_untyped_frame_meta_for!(HugePageFrameMeta);
+
+impl Drop for HugePageFrameMeta {
+    fn drop(&mut self) {
+        self.pool.uncharge_frames(1, self.subpool.as_deref());
+    }
+}
+
+impl HugePagePool {
+    const fn new(page_size: usize, sys_name: &'static str) -> Self {
+        Self {
+            page_size,
+            sys_name,
+            counters: SpinLock::new(PoolCounters {
+                nr_pages: 0,
+                nr_used_frames: 0,
+                nr_reserved: 0,
+            }),
+        }
+    }
+
+    /// Returns the size of the huge pages in bytes.
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    fn frames_per_page(&self) -> usize {
+        self.page_size / PAGE_SIZE
+    }
+
+    /// Returns the number of the huge pages in the pool.
+    pub fn nr_pages(&self) -> usize {
+        self.counters.disable_irq().lock().nr_pages
+    }
+
+    /// Returns the number of the huge pages that are not in use, including the reserved ones.
+    pub fn nr_free_pages(&self) -> usize {
+        let counters = self.counters.disable_irq().lock();
+        counters.nr_pages - counters.nr_used_frames.div_ceil(self.frames_per_page())
+    }
+
+    /// Returns the number of the huge pages that are reserved and not in use.
+    pub fn nr_reserved_pages(&self) -> usize {
+        self.counters.disable_irq().lock().nr_reserved
+    }
+
+    /// Changes the number of the huge pages in the pool.
+    ///
+    /// The pool cannot be larger than the physical memory, nor smaller than the huge pages that
+    /// are in use or reserved.
+    pub fn set_nr_pages(&self, nr_pages: usize) {
+        let mut counters = self.counters.disable_irq().lock();
+        let nr_in_use = counters.nr_used_frames.div_ceil(self.frames_per_page());
+        counters.nr_pages = nr_pages
+            .min(super::mem_total() / self.page_size)
+            .max(nr_in_use + counters.nr_reserved);
+    }
+
+    /// Charges the huge pages that are to be allocated to the pool and the subpool.
+    fn charge_pages(&self, nr_pages: usize, subpool: Option<&HugePageSubpool>) -> Result<()> {
+        let frames_per_page = self.frames_per_page();
+        let mut counters = self.counters.disable_irq().lock();
+
+        let mut nr_from_reserved = 0;
+        if let Some(subpool) = subpool {
+            let nr_used = subpool.nr_used_pages();
+            if subpool
+                .max_pages
+                .is_some_and(|max_pages| nr_used + nr_pages > max_pages)
+            {
+                return_errno_with_message!(
+                    Errno::ENOMEM,
+                    "the huge pages exceed the size of the hugetlbfs mount"
+                );
+            }
+            nr_from_reserved = subpool.min_pages.saturating_sub(nr_used).min(nr_pages);
+        }
+
+        let nr_available = counters.nr_pages
+            - counters.nr_used_frames.div_ceil(frames_per_page)
+            - counters.nr_reserved;
+        if nr_pages - nr_from_reserved > nr_available {
+            return_errno_with_message!(Errno::ENOMEM, "the huge pages are used up");
+        }
+
+        counters.nr_used_frames += nr_pages * frames_per_page;
+        counters.nr_reserved -= nr_from_reserved;
+        if let Some(subpool) = subpool {
+            subpool
+                .nr_used_frames
+                .fetch_add(nr_pages * frames_per_page, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    /// Returns the frames of the huge pages to the pool and the subpool.
+    fn uncharge_frames(&self, nr_frames: usize, subpool: Option<&HugePageSubpool>) {
+        let mut counters = self.counters.disable_irq().lock();
+        counters.nr_used_frames -= nr_frames;
+
+        if let Some(subpool) = subpool {
+            let nr_used_before = subpool.nr_used_pages();
+            subpool
+                .nr_used_frames
+                .fetch_sub(nr_frames, Ordering::Relaxed);
+            let nr_used_after = subpool.nr_used_pages();
+            // The pages below the minimum size of the mount are reserved again.
+            counters.nr_reserved +=
+                nr_used_before.min(subpool.min_pages) - nr_used_after.min(subpool.min_pages);
+        }
+    }
+
+    /// Allocates a VMO of the huge pages, whose size is rounded up to the huge page size.
+    pub fn alloc_vmo(
+        &'static self,
+        size: usize,
+        subpool: Option<&Arc<HugePageSubpool>>,
+    ) -> Result<Vmo<Rights>> {
+        let size = size.align_up(self.page_size);
+        let frames = self.alloc_pages(size / self.page_size, subpool)?;
+
+        let vmo = VmoOptions::<Rights>::new(size).alloc()?;
+        for (page_idx, frame) in frames.into_iter().enumerate() {
+            vmo.replace(frame, page_idx)?;
+        }
+        Ok(vmo)
+    }
+
+    /// Allocates the huge pages, which are returned as their frames in order.
+    pub fn alloc_pages(
+        &'static self,
+        nr_pages: usize,
+        subpool: Option<&Arc<HugePageSubpool>>,
+    ) -> Result<Vec<UFrame>> {
+        self.charge_pages(nr_pages, subpool.map(Arc::as_ref))?;
+
+        let mut frames = Vec::with_capacity(nr_pages * self.frames_per_page());
+        for nr_allocated in 0..nr_pages {
+            let segment =
+                FrameAllocOptions::new().alloc_segment_with(self.frames_per_page(), |_| {
+                    HugePageFrameMeta {
+                        pool: self,
+                        subpool: subpool.cloned(),
+                    }
+                });
+            let Ok(segment) = segment else {
+                // The allocated frames are uncharged when they are dropped.
+                self.uncharge_frames(
+                    (nr_pages - nr_allocated) * self.frames_per_page(),
+                    subpool.map(Arc::as_ref),
+                );
+                return_errno_with_message!(Errno::ENOMEM, "the huge pages cannot be allocated");
+            };
+            frames.extend(USegment::from(segment));
+        }
+
+        Ok(frames)
+    }
+}
+
+impl HugePageSubpool {
+    /// Creates a subpool that uses at most `max_size` bytes of the huge pages, and reserves
+    /// `min_size` bytes of them.
+    ///
+    /// The sizes are rounded down to the huge page size.
+    pub fn new(
+        pool: &'static HugePagePool,
+        max_size: Option<usize>,
+        min_size: usize,
+    ) -> Result<Arc<Self>> {
+        let max_pages = max_size.map(|max_size| max_size / pool.page_size);
+        let min_pages = min_size / pool.page_size;
+        if max_pages.is_some_and(|max_pages| min_pages > max_pages) {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the minimum size is larger than the size of the hugetlbfs mount"
+            );
+        }
+
+        let mut counters = pool.counters.disable_irq().lock();
+        let nr_available = counters.nr_pages
+            - counters.nr_used_frames.div_ceil(pool.frames_per_page())
+            - counters.nr_reserved;
+        if min_pages > nr_available {
+            return_errno_with_message!(
+                Errno::ENOMEM,
+                "the huge pages cannot be reserved for the hugetlbfs mount"
+            );
+        }
+        counters.nr_reserved += min_pages;
+
+        Ok(Arc::new(Self {
+            pool,
+            max_pages,
+            min_pages,
+            nr_used_frames: AtomicUsize::new(0),
+        }))
+    }
+
+    /// Returns the pool from which the huge pages are taken.
+    pub fn pool(&self) -> &'static HugePagePool {
+        self.pool
+    }
+
+    fn nr_used_pages(&self) -> usize {
+        self.nr_used_frames
+            .load(Ordering::Relaxed)
+            .div_ceil(self.pool.frames_per_page())
+    }
+}
+
+impl Drop for HugePageSubpool {
+    fn drop(&mut self) {
+        // The frames hold the subpool, so none of the huge pages are in use now.
+        self.pool.counters.disable_irq().lock().nr_reserved -= self.min_pages;
+    }
+}
+
+/// Returns the pool of the default huge page size.
+pub fn default_pool() -> &'static HugePagePool {
+    &POOLS[0]
+}
+
+/// Returns the pools of all the huge page sizes.
+pub fn pools() -> &'static [HugePagePool] {
+    &POOLS
+}
+
+/// Returns the pool of the huge page size, or the default pool if the size is zero.
+pub fn pool_of_size(page_size: usize) -> Result<&'static HugePagePool> {
+    if page_size == 0 {
+        return Ok(default_pool());
+    }
+    POOLS
+        .iter()
+        .find(|pool| pool.page_size == page_size)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the huge page size is not supported"))
+}
+
+/// Returns the pool of the huge page size encoded in the flags of `mmap` or `shmget`.
+pub fn pool_of_flags(flags: u32) -> Result<&'static HugePagePool> {
+    let shift = (flags >> HUGETLB_FLAG_ENCODE_SHIFT) & HUGETLB_FLAG_ENCODE_MASK;
+    pool_of_size(if shift == 0 { 0 } else { 1 << shift })
+}
+
+/// The `/sys/kernel/mm/hugepages/hugepages-<size>kB` directory in sysfs.
+#[derive(Debug)]
+struct HugePagesSysNode {
+    pool: &'static HugePagePool,
+    fields: SysNormalNodeFields,
+    this: Weak<HugePagesSysNode>,
+}
+
+/// Creates the nodes of the directories in `/sys/kernel/mm/hugepages` in sysfs.
+pub fn new_sys_nodes() -> impl Iterator<Item = Arc<dyn SysObj>> {
+    POOLS.iter().map(|pool| {
+        let mut builder = SysAttrSetBuilder::new();
+        builder.add(
+            Cow::Borrowed("nr_hugepages"),
+            SysAttrFlags::CAN_READ | SysAttrFlags::CAN_WRITE,
+        );
+        for name in ["free_hugepages", "resv_hugepages", "surplus_hugepages"] {
+            builder.add(Cow::Borrowed(name), SysAttrFlags::CAN_READ);
+        }
+
+        let fields =
+            SysNormalNodeFields::new(Cow::Borrowed(pool.sys_name), builder.build().unwrap());
+        Arc::new_cyclic(|this| HugePagesSysNode {
+            pool,
+            fields,
+            this: this.clone(),
+        }) as Arc<dyn SysObj>
+    })
+}
+
+impl SysObj for HugePagesSysNode {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn arc_as_node(&self) -> Option<Arc<dyn SysNode>> {
+        self.this.upgrade().map(|this| this as Arc<dyn SysNode>)
+    }
+
+    fn id(&self) -> &SysNodeId {
+        self.fields.id()
+    }
+
+    fn type_(&self) -> SysNodeType {
+        SysNodeType::Leaf
+    }
+
+    fn name(&self) -> SysStr {
+        Cow::Owned(self.fields.name().to_string())
+    }
+}
+
+impl SysNode for HugePagesSysNode {
+    fn node_attrs(&self) -> &SysAttrSet {
+        self.fields.attr_set()
+    }
+
+    fn read_attr(&self, name: &str, writer: &mut VmWriter) -> SysTreeResult<usize> {
+        let value = match name {
+            "nr_hugepages" => self.pool.nr_pages(),
+            "free_hugepages" => self.pool.nr_free_pages(),
+            "resv_hugepages" => self.pool.nr_reserved_pages(),
+            // The pool never grows beyond `nr_hugepages`.
+            "surplus_hugepages" => 0,
+            _ => return Err(SysTreeError::AttributeError),
+        };
+
+        let value = format!("{}\n", value);
+        writer
+            .write_fallible(&mut value.as_bytes().into())
+            .map_err(|_| SysTreeError::AttributeError)
+    }
+
+    fn write_attr(&self, name: &str, reader: &mut VmReader) -> SysTreeResult<usize> {
+        if name != "nr_hugepages" {
+            return Err(SysTreeError::PermissionDenied);
+        }
+
+        let mut buf = [0u8; 24];
+        let len = reader
+            .read_fallible(&mut VmWriter::from(buf.as_mut_slice()))
+            .map_err(|_| SysTreeError::AttributeError)?;
+        let value = core::str::from_utf8(&buf[..len])
+            .ok()
+            .and_then(|value| value.trim().parse::<usize>().ok())
+            .ok_or(SysTreeError::AttributeError)?;
+        self.pool.set_nr_pages(value);
+
+        Ok(len)
+    }
+}
@@ -19,8 +19,11 @@
 use osdk_frame_allocator::FrameAllocator;
 use osdk_heap_allocator::{type_from_layout, HeapAllocator};
 
+pub mod hugetlb;
+pub mod overcommit;
 pub mod page_fault_handler;
 pub mod perms;
+pub mod stat;
 pub mod util;
 pub mod vmar;
 pub mod vmo;
@@ -36,6 +39,11 @@ const fn slot_type_from_layout(layout: core::alloc::Layout) -> Option<ostd::mm::
     type_from_layout(layout)
 }
 
+/// Initializes the VM subsystem in the context of the init thread.
+pub fn lazy_init() {
+    vmar::ksm::init();
+}
+
 /// Total physical memory in the entire system in bytes.
 pub fn mem_total() -> usize {
     use ostd::boot::{boot_info, memory_region::MemoryRegionType};
@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Memory overcommit accounting.
+//!
+//! Like Linux, the private writable mappings (including the heap) are accounted as the committed
+//! memory when they are created, since their pages may be written and need to be backed by
+//! physical memory at any time. Whether the committed memory can exceed the physical memory is
+//! decided by the overcommit mode (`/proc/sys/vm/overcommit_memory`):
+//! - In the heuristic mode (the default), only a single allocation that is larger than the total
+//!   memory fails;
+//! - In the always mode, no allocation fails;
+//! - In the never mode, the total committed memory (`Committed_AS` in `/proc/meminfo`) cannot
+//!   exceed the commit limit (`CommitLimit` in `/proc/meminfo`).
+//!
+//! `MAP_NORESERVE` mappings are not accounted unless the never mode is used.
+//!
+//! Reference: <https://docs.kernel.org/mm/overcommit-accounting.html>
+
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use crate::prelude::*;
+
+/// The overcommit mode (`overcommit_memory`).
+static OVERCOMMIT_MEMORY: AtomicU32 = AtomicU32::new(OvercommitMode::Guess as u32);
+/// The percentage of the memory that can be committed in the never mode (`overcommit_ratio`).
+static OVERCOMMIT_RATIO: AtomicUsize = AtomicUsize::new(50);
+/// The KiB of the memory that can be committed in the never mode (`overcommit_kbytes`).
+///
+/// If this is not zero, it takes precedence over [`OVERCOMMIT_RATIO`].
+static OVERCOMMIT_KBYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// The total committed memory in bytes.
+static COMMITTED_AS: AtomicUsize = AtomicUsize::new(0);
+
+/// The overcommit mode.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub enum OvercommitMode {
+    /// Refuses only the obvious overcommits (`OVERCOMMIT_GUESS`).
+    Guess = 0,
+    /// Always overcommits (`OVERCOMMIT_ALWAYS`).
+    Always = 1,
+    /// Never overcommits (`OVERCOMMIT_NEVER`).
+    Never = 2,
+}
+
+/// Returns the overcommit mode.
+pub fn overcommit_memory() -> OvercommitMode {
+    OvercommitMode::try_from(OVERCOMMIT_MEMORY.load(Ordering::Relaxed)).unwrap()
+}
+
+/// Sets the overcommit mode.
+pub fn set_overcommit_memory(mode: OvercommitMode) {
+    OVERCOMMIT_MEMORY.store(mode as u32, Ordering::Relaxed);
+}
+
+/// Returns the percentage of the memory that can be committed in the never mode.
+pub fn overcommit_ratio() -> usize {
+    OVERCOMMIT_RATIO.load(Ordering::Relaxed)
+}
+
+/// Sets the percentage of the memory that can be committed in the never mode.
+///
+/// Like Linux, this clears the value set by [`set_overcommit_kbytes`].
+pub fn set_overcommit_ratio(ratio: usize) {
+    OVERCOMMIT_RATIO.store(ratio, Ordering::Relaxed);
+    OVERCOMMIT_KBYTES.store(0, Ordering::Relaxed);
+}
+
+/// Returns the KiB of the memory that can be committed in the never mode, or zero if the limit is
+/// decided by the ratio.
+pub fn overcommit_kbytes() -> usize {
+    OVERCOMMIT_KBYTES.load(Ordering::Relaxed)
+}
+
+/// Sets the KiB of the memory that can be committed in the never mode.
+///
+/// Like Linux, this clears the value set by [`set_overcommit_ratio`].
+pub fn set_overcommit_kbytes(kbytes: usize) {
+    OVERCOMMIT_KBYTES.store(kbytes, Ordering::Relaxed);
+    OVERCOMMIT_RATIO.store(0, Ordering::Relaxed);
+}
+
+/// Returns the maximum bytes of the committed memory in the never mode.
+pub fn commit_limit() -> usize {
+    let kbytes = overcommit_kbytes();
+    if kbytes != 0 {
+        return kbytes.saturating_mul(1024);
+    }
+
+    // There is no swap space, so the limit only depends on the physical memory.
+    super::mem_total() / 100 * overcommit_ratio()
+}
+
+/// Returns the total committed memory in bytes.
+pub fn committed_as() -> usize {
+    COMMITTED_AS.load(Ordering::Relaxed)
+}
+
+/// Checks whether `size` bytes of memory can be newly committed.
+///
+/// Like Linux, the check is done without accounting the memory, so concurrent allocations may
+/// slightly exceed the commit limit.
+pub(super) fn check_enough_memory(size: usize) -> Result<()> {
+    let is_enough = match overcommit_memory() {
+        OvercommitMode::Guess => size <= super::mem_total(),
+        OvercommitMode::Always => true,
+        OvercommitMode::Never => committed_as()
+            .checked_add(size)
+            .is_some_and(|committed| committed <= commit_limit()),
+    };
+
+    if !is_enough {
+        return_errno_with_message!(Errno::ENOMEM, "not enough memory to commit");
+    }
+    Ok(())
+}
+
+/// Accounts `size` bytes of memory as committed.
+pub(super) fn acct_memory(size: usize) {
+    COMMITTED_AS.fetch_add(size, Ordering::Relaxed);
+}
+
+/// Releases `size` bytes of committed memory.
+pub(super) fn unacct_memory(size: usize) {
+    COMMITTED_AS.fetch_sub(size, Ordering::Relaxed);
+}
@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! VM event counters.
+//!
+//! The counters are accumulated since boot and reported in `/proc/vmstat` with the same names as
+//! Linux.
+//!
+//! Reference: <https://github.com/torvalds/linux/blob/master/include/linux/vm_event_item.h>
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// A VM event that is counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VmEvent {
+    /// The pages that become dirty in the page cache.
+    NrDirtied,
+    /// The dirty pages that are written back from the page cache.
+    NrWritten,
+    /// The page faults.
+    PgFault,
+    /// The page faults that need to read the page from the storage.
+    PgMajFault,
+    /// The copy-on-write faults on the pages merged by KSM.
+    CowKsm,
+}
+
+impl VmEvent {
+    /// All the events, in the order of `/proc/vmstat`.
+    pub const ALL: [VmEvent; 5] = [
+        VmEvent::NrDirtied,
+        VmEvent::NrWritten,
+        VmEvent::PgFault,
+        VmEvent::PgMajFault,
+        VmEvent::CowKsm,
+    ];
+
+    /// Returns the name of the event in `/proc/vmstat`.
+    pub fn name(self) -> &'static str {
+        match self {
+            VmEvent::NrDirtied => "nr_dirtied",
+            VmEvent::NrWritten => "nr_written",
+            VmEvent::PgFault => "pgfault",
+            VmEvent::PgMajFault => "pgmajfault",
+            VmEvent::CowKsm => "cow_ksm",
+        }
+    }
+}
+
+static VM_EVENTS: [AtomicU64; VmEvent::ALL.len()] =
+    [const { AtomicU64::new(0) }; VmEvent::ALL.len()];
+
+/// Counts one occurrence of the event.
+pub fn count_vm_event(event: VmEvent) {
+    count_vm_events(event, 1);
+}
+
+/// Counts `nr` occurrences of the event.
+pub fn count_vm_events(event: VmEvent, nr: u64) {
+    VM_EVENTS[event as usize].fetch_add(nr, Ordering::Relaxed);
+}
+
+/// Returns the number of occurrences of the event since boot.
+pub fn vm_event(event: VmEvent) -> u64 {
+    VM_EVENTS[event as usize].load(Ordering::Relaxed)
+}
@@ -1,14 +1,45 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use ostd::mm::{Frame, FrameAllocOptions, UFrame, UntypedMem};
+use ostd::{
+    impl_untyped_frame_meta_for,
+    mm::{FrameAllocOptions, UFrame, UntypedMem},
+};
 
-use crate::prelude::*;
+use crate::{
+    prelude::*,
+    process::{cgroup::MemoryCharge, Process},
+};
 
-/// Creates a new `Frame<()>` and initializes it with the contents of the `src`.
+/// Creates a new `UFrame` for the user space and initializes it with the contents of the `src`.
 ///
 /// Note that it only duplicates the contents not the metadata.
-pub fn duplicate_frame(src: &UFrame) -> Result<Frame<()>> {
-    let new_frame = FrameAllocOptions::new().zeroed(false).alloc_frame()?;
+pub fn duplicate_frame(src: &UFrame) -> Result<UFrame> {
+    let new_frame = alloc_user_frame(false)?;
     new_frame.writer().write(&mut src.reader());
     Ok(new_frame)
 }
+
+/// Allocates a new frame for the user space.
+///
+/// The frame is charged to the cgroup of the current process, if any, until the frame is freed.
+pub fn alloc_user_frame(zeroed: bool) -> Result<UFrame> {
+    let mut options = FrameAllocOptions::new();
+    options.zeroed(zeroed);
+
+    let Some(process) = Process::current() else {
+        return Ok(options.alloc_frame()?.into());
+    };
+
+    let charge = process.cgroup().try_charge(PAGE_SIZE)?;
+    Ok(options
+        .alloc_frame_with(ChargedFrameMeta { _charge: charge })?
+        .into())
+}
+
+/// The metadata of a frame that is charged to a cgroup.
+#[derive(Debug)]
+struct ChargedFrameMeta {
+    _charge: MemoryCharge,
+}
+
+impl_untyped_frame_meta_for!(ChargedFrameMeta);
@@ -75,6 +75,19 @@ impl Vmar<Rights> {
         self.0.protect(perms, range)
     }
 
+    /// Sets whether the pages in the specified range can be merged with
+    /// identical pages by KSM.
+    ///
+    /// The range's start and end addresses must be page-aligned. Only the
+    /// private anonymous mappings are affected. If the pages become
+    /// unmergeable, the merged pages are copied back to private pages.
+    ///
+    /// If part of the range is not mapped, this method will return `Err`
+    /// after updating the mapped part.
+    pub fn set_mergeable(&self, range: Range<usize>, is_mergeable: bool) -> Result<()> {
+        self.0.set_mergeable(range, is_mergeable)
+    }
+
     /// Clears all mappings.
     ///
     /// After being cleared, this vmar will become an empty vmar
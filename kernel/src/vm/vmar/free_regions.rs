@@ -0,0 +1,269 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Free regions used to place new mappings in VMARs.
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
+use core::ops::Range;
+
+use ostd::mm::Vaddr;
+
+/// The free regions in an address range.
+///
+/// The regions are indexed by both their addresses and their sizes, so that
+/// occupying, releasing and searching for a free region all take logarithmic
+/// time in the number of regions.
+#[derive(Debug)]
+pub(super) struct FreeRegions {
+    /// The managed address range.
+    bounds: Range<Vaddr>,
+    /// The free regions, mapping the start address to the end address.
+    by_addr: BTreeMap<Vaddr, Vaddr>,
+    /// The free regions, ordered by their sizes and then their start addresses.
+    by_size: BTreeSet<(usize, Vaddr)>,
+    /// The address range that should be avoided when placing new mappings.
+    reserved: Option<Range<Vaddr>>,
+}
+
+impl FreeRegions {
+    /// Creates free regions that make up the whole `bounds`.
+    pub(super) fn new(bounds: Range<Vaddr>) -> Self {
+        let mut free_regions = Self {
+            bounds: bounds.clone(),
+            by_addr: BTreeMap::new(),
+            by_size: BTreeSet::new(),
+            reserved: None,
+        };
+        free_regions.add(bounds);
+        free_regions
+    }
+
+    /// Marks the whole address range as free and drops the reserved range.
+    pub(super) fn reset(&mut self) {
+        *self = Self::new(self.bounds.clone());
+    }
+
+    /// Returns the reserved range.
+    pub(super) fn reserved(&self) -> Option<Range<Vaddr>> {
+        self.reserved.clone()
+    }
+
+    /// Sets the reserved range, which is avoided by [`Self::find`] unless
+    /// there is no other room.
+    pub(super) fn set_reserved(&mut self, reserved: Option<Range<Vaddr>>) {
+        self.reserved = reserved;
+    }
+
+    /// Marks the address range as occupied.
+    ///
+    /// The part of the range that is not free or is out of the bounds is ignored.
+    pub(super) fn occupy(&mut self, range: Range<Vaddr>) {
+        // The first region that may intersect with the range is the one that
+        // starts at or before the range.
+        let first = self
+            .by_addr
+            .range(..=range.start)
+            .next_back()
+            .map_or(range.start, |(start, _)| *start);
+
+        let mut intersected = Vec::new();
+        for (start, end) in self.by_addr.range(first..range.end) {
+            if *end > range.start {
+                intersected.push(*start..*end);
+            }
+        }
+
+        for region in intersected {
+            self.delete(&region);
+            if region.start < range.start {
+                self.add(region.start..range.start);
+            }
+            if range.end < region.end {
+                self.add(range.end..region.end);
+            }
+        }
+    }
+
+    /// Marks the address range as free.
+    ///
+    /// The range must be occupied, except the part that is out of the bounds.
+    pub(super) fn release(&mut self, range: Range<Vaddr>) {
+        let mut start = range.start.max(self.bounds.start);
+        let mut end = range.end.min(self.bounds.end);
+        if start >= end {
+            return;
+        }
+
+        // Merge with the adjacent free regions.
+        if let Some((prev_start, prev_end)) = self.by_addr.range(..start).next_back() {
+            debug_assert!(*prev_end <= start);
+            if *prev_end == start {
+                let prev = *prev_start..*prev_end;
+                self.delete(&prev);
+                start = prev.start;
+            }
+        }
+        if let Some(next_end) = self.by_addr.get(&end).copied() {
+            let next = end..next_end;
+            self.delete(&next);
+            end = next.end;
+        }
+
+        self.add(start..end);
+    }
+
+    /// Finds a free region of `size` bytes that is aligned to `align`.
+    ///
+    /// The region is taken from the highest free region if it is at the end of
+    /// the bounds, so that the new mappings are placed in order. Otherwise, the
+    /// smallest free region that fits is used to reduce fragmentation.
+    ///
+    /// The reserved range is used only if no other free region fits.
+    pub(super) fn find(&self, size: usize, align: usize) -> Option<Range<Vaddr>> {
+        self.find_in(size, align, self.reserved.as_ref())
+            .or_else(|| self.find_in(size, align, None))
+    }
+
+    fn find_in(
+        &self,
+        size: usize,
+        align: usize,
+        reserved: Option<&Range<Vaddr>>,
+    ) -> Option<Range<Vaddr>> {
+        let fits_in = |start: Vaddr, end: Vaddr| {
+            debug_assert!(align.is_power_of_two());
+            let aligned = start.checked_add(align - 1)? & !(align - 1);
+            let needed_end = aligned.checked_add(size)?;
+            (needed_end <= end).then_some(aligned..needed_end)
+        };
+        // Try the parts below and above the reserved range if they intersect.
+        let fits = |start: Vaddr, end: Vaddr| match reserved {
+            Some(reserved) if reserved.start < end && start < reserved.end => {
+                fits_in(start, reserved.start.max(start))
+                    .or_else(|| fits_in(reserved.end.min(end), end))
+            }
+            _ => fits_in(start, end),
+        };
+
+        // Fast path that there's still room to the end.
+        if let Some((start, end)) = self.by_addr.last_key_value() {
+            if *end == self.bounds.end {
+                if let Some(region) = fits(*start, *end) {
+                    return Some(region);
+                }
+            }
+        }
+
+        // Slow path that we need to search for the smallest fitting region.
+        // A region that is large enough may still not fit due to alignment,
+        // but any region of `size + align - PAGE_SIZE` bytes must fit, so the
+        // iteration ends just after such a region is visited.
+        self.by_size
+            .range((size, 0)..)
+            .find_map(|(len, start)| fits(*start, *start + *len))
+    }
+
+    fn add(&mut self, region: Range<Vaddr>) {
+        debug_assert!(region.start < region.end);
+        self.by_addr.insert(region.start, region.end);
+        self.by_size.insert((region.len(), region.start));
+    }
+
+    fn delete(&mut self, region: &Range<Vaddr>) {
+        self.by_addr.remove(&region.start);
+        self.by_size.remove(&(region.len(), region.start));
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::{mm::PAGE_SIZE, prelude::ktest};
+
+    use super::*;
+
+    const BOUNDS: Range<Vaddr> = PAGE_SIZE..PAGE_SIZE * 100;
+
+    fn page_range(start: usize, end: usize) -> Range<Vaddr> {
+        start * PAGE_SIZE..end * PAGE_SIZE
+    }
+
+    #[ktest]
+    fn find_at_the_end() {
+        let mut regions = FreeRegions::new(BOUNDS);
+        assert_eq!(
+            regions.find(PAGE_SIZE * 2, PAGE_SIZE),
+            Some(page_range(1, 3))
+        );
+
+        regions.occupy(page_range(1, 3));
+        regions.occupy(page_range(10, 20));
+        assert_eq!(regions.find(PAGE_SIZE, PAGE_SIZE), Some(page_range(20, 21)));
+        assert_eq!(
+            regions.find(PAGE_SIZE * 16, PAGE_SIZE * 16),
+            Some(page_range(32, 48))
+        );
+        assert_eq!(regions.find(PAGE_SIZE * 100, PAGE_SIZE), None);
+    }
+
+    #[ktest]
+    fn find_smallest_hole() {
+        let mut regions = FreeRegions::new(BOUNDS);
+        regions.occupy(page_range(1, 10));
+        regions.occupy(page_range(12, 20));
+        regions.occupy(page_range(23, 99));
+
+        // Both holes fit, and the smaller one is preferred.
+        assert_eq!(
+            regions.find(PAGE_SIZE * 2, PAGE_SIZE),
+            Some(page_range(10, 12))
+        );
+        assert_eq!(
+            regions.find(PAGE_SIZE * 3, PAGE_SIZE),
+            Some(page_range(20, 23))
+        );
+        assert_eq!(regions.find(PAGE_SIZE * 4, PAGE_SIZE), None);
+    }
+
+    #[ktest]
+    fn release_and_merge() {
+        let mut regions = FreeRegions::new(BOUNDS);
+        regions.occupy(BOUNDS);
+        assert_eq!(regions.find(PAGE_SIZE, PAGE_SIZE), None);
+
+        regions.release(page_range(10, 12));
+        regions.release(page_range(14, 16));
+        regions.release(page_range(12, 14));
+        assert_eq!(regions.by_addr.len(), 1);
+        assert_eq!(
+            regions.find(PAGE_SIZE * 6, PAGE_SIZE),
+            Some(page_range(10, 16))
+        );
+
+        // The occupied range may cover multiple regions and the out-of-bounds addresses.
+        regions.release(page_range(20, 30));
+        regions.occupy(page_range(0, 25));
+        assert_eq!(
+            regions.find(PAGE_SIZE * 5, PAGE_SIZE),
+            Some(page_range(25, 30))
+        );
+        assert_eq!(regions.find(PAGE_SIZE * 6, PAGE_SIZE), None);
+
+        regions.reset();
+        assert_eq!(regions.reserved(), None);
+        assert_eq!(regions.find(PAGE_SIZE * 99, PAGE_SIZE), Some(BOUNDS));
+    }
+
+    #[ktest]
+    fn avoid_reserved() {
+        let mut regions = FreeRegions::new(BOUNDS);
+        regions.occupy(page_range(1, 10));
+        regions.set_reserved(Some(page_range(10, 20)));
+
+        assert_eq!(regions.find(PAGE_SIZE, PAGE_SIZE), Some(page_range(20, 21)));
+        regions.occupy(page_range(20, 100));
+        // The reserved range is used as the last resort.
+        assert_eq!(regions.find(PAGE_SIZE, PAGE_SIZE), Some(page_range(10, 11)));
+    }
+}
@@ -0,0 +1,543 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Kernel samepage merging (KSM).
+//!
+//! KSM merges the identical pages in the private anonymous mappings that are marked with
+//! `madvise(MADV_MERGEABLE)`. A background thread periodically scans the pages of the VMARs that
+//! have such mappings, and replaces each page whose content is identical to a merged page (a.k.a.
+//! a KSM page) with a read-only mapping of the KSM page. A later write access to the page gets a
+//! private copy through the usual copy-on-write (COW) path.
+//!
+//! This is a simplified version of KSM in Linux:
+//! - The KSM pages are indexed by the checksums of their contents (i.e., the stable table), and the
+//!   candidates are remembered only by their checksums during one full scan (i.e., the unstable
+//!   set). So a KSM page is created when the second page with the same checksum is met, and the
+//!   first page is merged with the KSM page during the next full scan.
+//! - The pages that are filled with zeros are always merged with a single zero page, as if
+//!   `use_zero_pages` is enabled in Linux.
+//!
+//! The thread is stopped by default. It is controlled through the files in `/sys/kernel/mm/ksm`.
+//!
+//! Reference: <https://docs.kernel.org/admin-guide/mm/ksm.html>
+
+use alloc::{borrow::Cow, format};
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use aster_systree::{
+    Error as SysTreeError, Result as SysTreeResult, SysAttrFlags, SysAttrSet, SysAttrSetBuilder,
+    SysNode, SysNodeId, SysNodeType, SysNormalNodeFields, SysObj, SysStr,
+};
+use ostd::{
+    impl_untyped_frame_meta_for,
+    mm::{
+        tlb::TlbFlushOp, vm_space::VmItem, FrameAllocOptions, PageFlags, UFrame, UntypedMem,
+        VmSpace,
+    },
+    sync::WaitQueue,
+    task::disable_preempt,
+};
+use spin::Once;
+
+use super::{interval_set::Interval, vm_mapping::VmMapping, Vmar_};
+use crate::{
+    prelude::*,
+    sched::{Nice, SchedPolicy},
+    thread::kernel_thread::ThreadOptions,
+    vm::util::duplicate_frame,
+};
+
+/// The VMARs that may have mergeable mappings.
+static KSM_VMARS: Mutex<Vec<Weak<Vmar_>>> = Mutex::new(Vec::new());
+
+/// The page that is filled with zeros and shared by all the merged zero pages.
+static ZERO_FRAME: Once<UFrame> = Once::new();
+
+static KSMD_WAIT_QUEUE: WaitQueue = WaitQueue::new();
+
+/// The lock that serializes the scans of the KSM thread and the unmerging of all the pages.
+static SCAN_LOCK: Mutex<()> = Mutex::new(());
+
+/// Whether the KSM thread is running (`run`).
+static RUN: AtomicU32 = AtomicU32::new(KsmRun::Stop as u32);
+/// The number of pages to scan before the KSM thread sleeps (`pages_to_scan`).
+static PAGES_TO_SCAN: AtomicU32 = AtomicU32::new(100);
+/// The milliseconds that the KSM thread sleeps between two batches (`sleep_millisecs`).
+static SLEEP_MILLISECS: AtomicU32 = AtomicU32::new(20);
+
+/// The number of the KSM pages that are in use (`pages_shared`).
+static PAGES_SHARED: AtomicUsize = AtomicUsize::new(0);
+/// The number of the extra mappings of the KSM pages (`pages_sharing`).
+static PAGES_SHARING: AtomicUsize = AtomicUsize::new(0);
+/// The number of the mappings of the zero page (`ksm_zero_pages`).
+static KSM_ZERO_PAGES: AtomicUsize = AtomicUsize::new(0);
+/// The number of the times that all the mergeable pages are scanned (`full_scans`).
+static FULL_SCANS: AtomicUsize = AtomicUsize::new(0);
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+enum KsmRun {
+    /// Stops merging pages, but keeps the merged pages.
+    Stop = 0,
+    /// Runs the KSM thread.
+    Run = 1,
+    /// Stops merging pages, and copies all the merged pages back to private pages.
+    Unmerge = 2,
+}
+
+/// The metadata of a KSM page.
+#[derive(Debug)]
+struct KsmFrameMeta;
+
+impl_untyped_frame_meta_for!(KsmFrameMeta);
+
+/// Returns whether the frame is a KSM page.
+///
+/// A KSM page may be shared by many mappings, so it must not be written in place even if it
+/// seems to be referenced only once.
+pub(super) fn is_ksm_frame(frame: &UFrame) -> bool {
+    (frame.dyn_meta() as &dyn Any).is::<KsmFrameMeta>()
+}
+
+fn alloc_ksm_frame(content: &[u8]) -> Result<UFrame> {
+    let frame: UFrame = FrameAllocOptions::new()
+        .zeroed(false)
+        .alloc_frame_with(KsmFrameMeta)?
+        .into();
+    frame.writer().write(&mut VmReader::from(content));
+    Ok(frame)
+}
+
+/// Registers a VMAR whose mappings may be merged.
+///
+/// This should be called with the VMAR locked, before the VMAR gets mergeable mappings.
+pub(super) fn register(vmar: &Arc<Vmar_>) {
+    let mut vmars = KSM_VMARS.lock();
+    let vmar = Arc::downgrade(vmar);
+    if !vmars.iter().any(|registered| registered.ptr_eq(&vmar)) {
+        vmars.push(vmar);
+    }
+}
+
+/// Copies the KSM pages within the range back to private pages.
+///
+/// The new pages are still mapped as read-only, so the next write access makes them writable
+/// without copying.
+pub(super) fn unmerge_range(vm_space: &VmSpace, range: &Range<Vaddr>) -> Result<()> {
+    let preempt_guard = disable_preempt();
+    let mut cursor = vm_space.cursor_mut(&preempt_guard, range)?;
+
+    loop {
+        let next_addr = match cursor.query()? {
+            VmItem::Mapped { va, frame, prop } if is_ksm_frame(&frame) => {
+                cursor.map(duplicate_frame(&frame)?, prop);
+                va + PAGE_SIZE
+            }
+            VmItem::Mapped { va, .. } => va + PAGE_SIZE,
+            VmItem::NotMapped { va, len } => va + len,
+        };
+        if next_addr >= range.end {
+            break;
+        }
+        cursor.jump(next_addr)?;
+    }
+    cursor.flusher().sync_tlb_flush();
+
+    Ok(())
+}
+
+/// Copies the KSM pages in all the registered VMARs back to private pages.
+fn unmerge_all() -> Result<()> {
+    let _scan_guard = SCAN_LOCK.lock();
+
+    let vmars: Vec<_> = KSM_VMARS.lock().iter().filter_map(Weak::upgrade).collect();
+    for vmar in vmars {
+        let inner = vmar.inner.read();
+        for vm_mapping in inner.vm_mappings.iter() {
+            if vm_mapping.is_mergeable() {
+                unmerge_range(&vmar.vm_space, &vm_mapping.range())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Spawns the KSM thread.
+pub fn init() {
+    ZERO_FRAME.call_once(|| {
+        FrameAllocOptions::new()
+            .alloc_frame_with(KsmFrameMeta)
+            .unwrap()
+            .into()
+    });
+
+    ThreadOptions::new(|| {
+        let mut ksmd = Ksmd::new();
+        loop {
+            let _ = KSMD_WAIT_QUEUE
+                .wait_until(|| (RUN.load(Ordering::Relaxed) == KsmRun::Run as u32).then_some(()));
+
+            ksmd.scan(PAGES_TO_SCAN.load(Ordering::Relaxed) as usize);
+
+            // Sleep until the interval elapses, or until the thread is stopped.
+            let sleep_duration =
+                Duration::from_millis(SLEEP_MILLISECS.load(Ordering::Relaxed) as u64);
+            let _ = KSMD_WAIT_QUEUE.wait_until_or_timeout(
+                || (RUN.load(Ordering::Relaxed) != KsmRun::Run as u32).then_some(()),
+                &sleep_duration,
+            );
+        }
+    })
+    .sched_policy(SchedPolicy::Fair(Nice::default()))
+    .spawn();
+}
+
+/// The states of the KSM thread.
+struct Ksmd {
+    /// The KSM pages indexed by the checksums of their contents.
+    stable_table: BTreeMap<u64, Vec<UFrame>>,
+    /// The checksums of the pages that have been scanned during the current full scan.
+    unstable_set: BTreeSet<u64>,
+    /// The index of the next VMAR to scan in [`KSM_VMARS`].
+    vmar_index: usize,
+    /// The next address to scan in the VMAR.
+    next_addr: Vaddr,
+    /// The buffer to compare the contents of two pages.
+    buffer: Box<[u8]>,
+}
+
+impl Ksmd {
+    fn new() -> Self {
+        Self {
+            stable_table: BTreeMap::new(),
+            unstable_set: BTreeSet::new(),
+            vmar_index: 0,
+            next_addr: 0,
+            buffer: vec![0; PAGE_SIZE * 2].into_boxed_slice(),
+        }
+    }
+
+    /// Scans at most `nr_pages` pages and updates the statistics.
+    fn scan(&mut self, nr_pages: usize) {
+        let _scan_guard = SCAN_LOCK.lock();
+        let mut budget = nr_pages;
+
+        while budget > 0 {
+            let vmar = {
+                let mut vmars = KSM_VMARS.lock();
+                if self.vmar_index >= vmars.len() {
+                    if vmars.is_empty() {
+                        break;
+                    }
+                    self.start_full_scan();
+                }
+                let vmar = vmars[self.vmar_index].upgrade();
+                if vmar.is_none() {
+                    vmars.remove(self.vmar_index);
+                    self.next_addr = 0;
+                }
+                vmar
+            };
+
+            if let Some(vmar) = vmar {
+                self.scan_vmar(&vmar, &mut budget);
+            }
+        }
+
+        self.update_stats();
+    }
+
+    fn start_full_scan(&mut self) {
+        self.vmar_index = 0;
+        self.next_addr = 0;
+        self.unstable_set.clear();
+        FULL_SCANS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Scans the mergeable pages in the VMAR until the budget runs out.
+    fn scan_vmar(&mut self, vmar: &Arc<Vmar_>, budget: &mut usize) {
+        let inner = vmar.inner.read();
+
+        for vm_mapping in inner.vm_mappings.find(&(self.next_addr..usize::MAX)) {
+            if !vm_mapping.is_mergeable() {
+                continue;
+            }
+
+            let range = vm_mapping.range();
+            let mut addr = self.next_addr.max(range.start);
+            while addr < range.end {
+                if *budget == 0 {
+                    self.next_addr = addr;
+                    return;
+                }
+                *budget -= 1;
+
+                addr = self.scan_page(&vmar.vm_space, addr, range.end);
+            }
+        }
+
+        // The VMAR is unregistered if it no longer has mergeable mappings. This is done with the
+        // VMAR locked, so that it cannot race with `register`. The VMARs are only removed by the
+        // KSM thread, so the index of the VMAR stays unchanged.
+        if inner.vm_mappings.iter().any(VmMapping::is_mergeable) {
+            self.vmar_index += 1;
+        } else {
+            KSM_VMARS.lock().remove(self.vmar_index);
+        }
+        self.next_addr = 0;
+    }
+
+    /// Scans the page at `addr` and tries to merge it.
+    ///
+    /// Returns the next address to scan, which is after the page, or after the unmapped area that
+    /// contains the address.
+    fn scan_page(&mut self, vm_space: &VmSpace, addr: Vaddr, end: Vaddr) -> Vaddr {
+        let frame = {
+            let preempt_guard = disable_preempt();
+            let Ok(mut cursor) = vm_space.cursor(&preempt_guard, &(addr..end)) else {
+                return end;
+            };
+            match cursor.query() {
+                Ok(VmItem::Mapped { frame, .. }) => frame,
+                Ok(VmItem::NotMapped { va, len }) => return (va + len).min(end),
+                Err(_) => return end,
+            }
+        };
+        let next_addr = addr + PAGE_SIZE;
+
+        if is_ksm_frame(&frame) {
+            return next_addr;
+        }
+
+        let content = &mut self.buffer[..PAGE_SIZE];
+        frame.reader().read(&mut VmWriter::from(&mut *content));
+
+        if content.iter().all(|byte| *byte == 0) {
+            let zero_frame = ZERO_FRAME.get().unwrap();
+            replace_page(vm_space, addr, &frame, zero_frame, &mut self.buffer);
+            return next_addr;
+        }
+
+        let checksum = calc_checksum(content);
+
+        if let Some(ksm_frames) = self.stable_table.get(&checksum) {
+            for ksm_frame in ksm_frames.iter() {
+                if replace_page(vm_space, addr, &frame, ksm_frame, &mut self.buffer) {
+                    return next_addr;
+                }
+            }
+        }
+
+        // Wait for another page with the same checksum during this full scan.
+        if self.unstable_set.insert(checksum) {
+            return next_addr;
+        }
+
+        let Ok(ksm_frame) = alloc_ksm_frame(&self.buffer[..PAGE_SIZE]) else {
+            return next_addr;
+        };
+        if replace_page(vm_space, addr, &frame, &ksm_frame, &mut self.buffer) {
+            self.stable_table
+                .entry(checksum)
+                .or_default()
+                .push(ksm_frame);
+        }
+
+        next_addr
+    }
+
+    /// Drops the KSM pages that are no longer mapped and updates the statistics.
+    fn update_stats(&mut self) {
+        let mut pages_shared = 0;
+        let mut pages_sharing = 0;
+
+        self.stable_table.retain(|_, ksm_frames| {
+            // One reference is held by the stable table.
+            ksm_frames.retain(|ksm_frame| ksm_frame.reference_count() > 1);
+            for ksm_frame in ksm_frames.iter() {
+                pages_shared += 1;
+                pages_sharing += ksm_frame.reference_count() as usize - 2;
+            }
+            !ksm_frames.is_empty()
+        });
+
+        let zero_frame = ZERO_FRAME.get().unwrap();
+
+        PAGES_SHARED.store(pages_shared, Ordering::Relaxed);
+        PAGES_SHARING.store(pages_sharing, Ordering::Relaxed);
+        KSM_ZERO_PAGES.store(zero_frame.reference_count() as usize - 1, Ordering::Relaxed);
+    }
+}
+
+/// Replaces the page at `addr` that maps `old_frame` with a read-only mapping of `ksm_frame`.
+///
+/// The page is write-protected before it is compared with the KSM page, so its content cannot
+/// change after the comparison. Returns `false` if the page is no longer mapped to `old_frame`
+/// or its content differs from the KSM page.
+fn replace_page(
+    vm_space: &VmSpace,
+    addr: Vaddr,
+    old_frame: &UFrame,
+    ksm_frame: &UFrame,
+    buffer: &mut [u8],
+) -> bool {
+    let preempt_guard = disable_preempt();
+    let Ok(mut cursor) = vm_space.cursor_mut(&preempt_guard, &(addr..addr + PAGE_SIZE)) else {
+        return false;
+    };
+    let Ok(VmItem::Mapped {
+        frame, mut prop, ..
+    }) = cursor.query()
+    else {
+        return false;
+    };
+    if frame.start_paddr() != old_frame.start_paddr() {
+        return false;
+    }
+
+    if prop.flags.contains(PageFlags::W) {
+        cursor.protect_next(PAGE_SIZE, |p| p.flags -= PageFlags::W);
+        cursor.flusher().issue_tlb_flush(TlbFlushOp::Address(addr));
+        cursor.flusher().dispatch_tlb_flush();
+        cursor.flusher().sync_tlb_flush();
+        cursor.jump(addr).unwrap();
+    }
+
+    let (old_content, ksm_content) = buffer.split_at_mut(PAGE_SIZE);
+    frame.reader().read(&mut VmWriter::from(old_content));
+    ksm_frame
+        .reader()
+        .read(&mut VmWriter::from(&mut *ksm_content));
+    if old_content != ksm_content {
+        return false;
+    }
+
+    prop.flags -= PageFlags::W;
+    cursor.map(ksm_frame.clone(), prop);
+    cursor.flusher().sync_tlb_flush();
+
+    true
+}
+
+/// Calculates the checksum of a page with the FNV-1a hash.
+fn calc_checksum(content: &[u8]) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    content.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// The `/sys/kernel/mm/ksm` directory in sysfs.
+#[derive(Debug)]
+struct KsmSysNode {
+    fields: SysNormalNodeFields,
+    this: Weak<KsmSysNode>,
+}
+
+/// Creates the node of the `/sys/kernel/mm/ksm` directory in sysfs.
+pub fn new_sys_node() -> Arc<dyn SysObj> {
+    let mut builder = SysAttrSetBuilder::new();
+    for name in ["run", "pages_to_scan", "sleep_millisecs"] {
+        builder.add(
+            Cow::Borrowed(name),
+            SysAttrFlags::CAN_READ | SysAttrFlags::CAN_WRITE,
+        );
+    }
+    for name in [
+        "pages_shared",
+        "pages_sharing",
+        "ksm_zero_pages",
+        "full_scans",
+    ] {
+        builder.add(Cow::Borrowed(name), SysAttrFlags::CAN_READ);
+    }
+
+    let fields = SysNormalNodeFields::new(Cow::Borrowed("ksm"), builder.build().unwrap());
+    Arc::new_cyclic(|this| KsmSysNode {
+        fields,
+        this: this.clone(),
+    })
+}
+
+impl SysObj for KsmSysNode {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn arc_as_node(&self) -> Option<Arc<dyn SysNode>> {
+        self.this.upgrade().map(|this| this as Arc<dyn SysNode>)
+    }
+
+    fn id(&self) -> &SysNodeId {
+        self.fields.id()
+    }
+
+    fn type_(&self) -> SysNodeType {
+        SysNodeType::Leaf
+    }
+
+    fn name(&self) -> SysStr {
+        Cow::Owned(self.fields.name().to_string())
+    }
+}
+
+impl SysNode for KsmSysNode {
+    fn node_attrs(&self) -> &SysAttrSet {
+        self.fields.attr_set()
+    }
+
+    fn read_attr(&self, name: &str, writer: &mut VmWriter) -> SysTreeResult<usize> {
+        let value = match name {
+            "run" => RUN.load(Ordering::Relaxed) as usize,
+            "pages_to_scan" => PAGES_TO_SCAN.load(Ordering::Relaxed) as usize,
+            "sleep_millisecs" => SLEEP_MILLISECS.load(Ordering::Relaxed) as usize,
+            "pages_shared" => PAGES_SHARED.load(Ordering::Relaxed),
+            "pages_sharing" => PAGES_SHARING.load(Ordering::Relaxed),
+            "ksm_zero_pages" => KSM_ZERO_PAGES.load(Ordering::Relaxed),
+            "full_scans" => FULL_SCANS.load(Ordering::Relaxed),
+            _ => return Err(SysTreeError::AttributeError),
+        };
+
+        let value = format!("{}\n", value);
+        writer
+            .write_fallible(&mut value.as_bytes().into())
+            .map_err(|_| SysTreeError::AttributeError)
+    }
+
+    fn write_attr(&self, name: &str, reader: &mut VmReader) -> SysTreeResult<usize> {
+        let mut buf = [0u8; 16];
+        let len = reader
+            .read_fallible(&mut VmWriter::from(buf.as_mut_slice()))
+            .map_err(|_| SysTreeError::AttributeError)?;
+        let value = core::str::from_utf8(&buf[..len])
+            .ok()
+            .and_then(|value| value.trim().parse::<u32>().ok())
+            .ok_or(SysTreeError::AttributeError)?;
+
+        match name {
+            "run" => {
+                let run = KsmRun::try_from(value).map_err(|_| SysTreeError::AttributeError)?;
+                if run == KsmRun::Unmerge {
+                    RUN.store(KsmRun::Stop as u32, Ordering::Relaxed);
+                    unmerge_all().map_err(|_| SysTreeError::AttributeError)?;
+                    PAGES_SHARED.store(0, Ordering::Relaxed);
+                    PAGES_SHARING.store(0, Ordering::Relaxed);
+                    KSM_ZERO_PAGES.store(0, Ordering::Relaxed);
+                }
+                RUN.store(run as u32, Ordering::Relaxed);
+                KSMD_WAIT_QUEUE.wake_all();
+            }
+            "pages_to_scan" => PAGES_TO_SCAN.store(value, Ordering::Relaxed),
+            "sleep_millisecs" => SLEEP_MILLISECS.store(value, Ordering::Relaxed),
+            _ => return Err(SysTreeError::PermissionDenied),
+        }
+
+        Ok(len)
+    }
+}
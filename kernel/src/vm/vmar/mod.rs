@@ -3,7 +3,9 @@
 //! Virtual Memory Address Regions (VMARs).
 
 mod dyn_cap;
+mod free_regions;
 mod interval_set;
+pub mod ksm;
 mod static_cap;
 pub mod vm_mapping;
 
@@ -12,20 +14,26 @@ use core::{num::NonZeroUsize, ops::Range};
 use align_ext::AlignExt;
 use aster_rights::Rights;
 use ostd::{
-    mm::{tlb::TlbFlushOp, PageFlags, PageProperty, VmSpace, MAX_USERSPACE_VADDR},
+    mm::{
+        tlb::TlbFlushOp, vm_space::VmItem, PageFlags, PageProperty, VmSpace, MAX_USERSPACE_VADDR,
+    },
     task::disable_preempt,
 };
 
 use self::{
+    free_regions::FreeRegions,
     interval_set::{Interval, IntervalSet},
     vm_mapping::{MappedVmo, VmMapping},
 };
 use crate::{
+    fs::path::Dentry,
     prelude::*,
     process::{Process, ResourceType},
     thread::exception::PageFaultInfo,
     vm::{
+        overcommit::{self, OvercommitMode},
         perms::VmPerms,
+        stat::{self, VmEvent},
         vmo::{Vmo, VmoRightsOp},
     },
 };
@@ -91,6 +99,57 @@ impl<R> Vmar<R> {
     pub fn resize_mapping(&self, map_addr: Vaddr, old_size: usize, new_size: usize) -> Result<()> {
         self.0.resize_mapping(map_addr, old_size, new_size)
     }
+
+    /// Reserves an address range for future mappings, e.g., the growth of the
+    /// user heap.
+    ///
+    /// New mappings whose addresses are not specified are placed outside the
+    /// reserved range, unless there is no other room. Only one range can be
+    /// reserved at a time, and the reservation is dropped when the VMAR is
+    /// cleared.
+    pub fn set_reserved_region(&self, range: Option<Range<Vaddr>>) {
+        self.0.inner.write().free_regions.set_reserved(range);
+    }
+
+    /// Synchronizes the shared file mappings within the range with the files.
+    ///
+    /// The pages written through the mappings are marked dirty in the page
+    /// cache. If `write_back` is true, the dirty pages are also written back
+    /// to the files before this method returns.
+    ///
+    /// If part of the range is not mapped, this method will return `Err`
+    /// after synchronizing the mapped part.
+    pub fn sync(&self, range: Range<Vaddr>, write_back: bool) -> Result<()> {
+        self.0.sync(range, write_back)
+    }
+
+    /// Checks whether the user memory within the range can be accessed with
+    /// the permissions.
+    ///
+    /// Returns `Err` with `EFAULT` if part of the range is not mapped, or is
+    /// mapped without the permissions.
+    pub fn check_access(&self, range: Range<Vaddr>, perms: VmPerms) -> Result<()> {
+        self.0.check_access(range, perms)
+    }
+
+    /// Writes bytes to the user memory at `vaddr`.
+    ///
+    /// Unlike the writers of [`VmSpace`], this method does not require the
+    /// VMAR to be activated on the current CPU, so kernel threads can use it
+    /// to write to the memory of a process. The pages that are absent or not
+    /// writable are faulted in as if they were written by the user.
+    pub fn write_bytes(&self, vaddr: Vaddr, buf: &[u8]) -> Result<()> {
+        self.0.write_bytes(vaddr, buf)
+    }
+
+    /// Reads bytes from the user memory at `vaddr`.
+    ///
+    /// Like [`Self::write_bytes`], this method does not require the VMAR to
+    /// be activated on the current CPU. The pages that are absent are faulted
+    /// in as if they were read by the user.
+    pub fn read_bytes(&self, vaddr: Vaddr, buf: &mut [u8]) -> Result<()> {
+        self.0.read_bytes(vaddr, buf)
+    }
 }
 
 pub(super) struct Vmar_ {
@@ -107,15 +166,21 @@ pub(super) struct Vmar_ {
 struct VmarInner {
     /// The mapped pages and associated metadata.
     vm_mappings: IntervalSet<Vaddr, VmMapping>,
+    /// The free regions where new mappings can be placed.
+    free_regions: FreeRegions,
     /// The total mapped memory in bytes.
     total_vm: usize,
+    /// The total memory in bytes that is accounted as committed memory.
+    committed_vm: usize,
 }
 
 impl VmarInner {
-    const fn new() -> Self {
+    fn new() -> Self {
         Self {
             vm_mappings: IntervalSet::new(),
+            free_regions: FreeRegions::new(ROOT_VMAR_LOWEST_ADDR..ROOT_VMAR_CAP_ADDR),
             total_vm: 0,
+            committed_vm: 0,
         }
     }
 
@@ -148,16 +213,67 @@ impl VmarInner {
     /// Make sure the insertion doesn't exceed address space limit.
     fn insert(&mut self, vm_mapping: VmMapping) {
         self.total_vm += vm_mapping.map_size();
+        if vm_mapping.is_accounted() {
+            self.committed_vm += vm_mapping.map_size();
+            overcommit::acct_memory(vm_mapping.map_size());
+        }
+        self.free_regions.occupy(vm_mapping.range());
         self.vm_mappings.insert(vm_mapping);
     }
 
+    /// Inserts a `VmMapping` into the `Vmar`, and merges it with the adjacent
+    /// `VmMapping`s if possible.
+    ///
+    /// Make sure the insertion doesn't exceed address space limit.
+    fn insert_and_merge(&mut self, mut vm_mapping: VmMapping) {
+        let range = vm_mapping.range();
+
+        let prev_addr = range
+            .start
+            .checked_sub(1)
+            .and_then(|prev_end| self.vm_mappings.find_one(&prev_end))
+            .filter(|prev| prev.can_merge_with(&vm_mapping))
+            .map(|prev| prev.map_to_addr());
+        if let Some(prev_addr) = prev_addr {
+            let prev = self.remove(&prev_addr).unwrap();
+            vm_mapping = prev.merge(vm_mapping);
+        }
+
+        let has_next = self
+            .vm_mappings
+            .find_one(&range.end)
+            .is_some_and(|next| vm_mapping.can_merge_with(next));
+        if has_next {
+            let next = self.remove(&range.end).unwrap();
+            vm_mapping = vm_mapping.merge(next);
+        }
+
+        self.insert(vm_mapping);
+    }
+
     /// Removes a `VmMapping` based on the provided key from the `Vmar`.
     fn remove(&mut self, key: &Vaddr) -> Option<VmMapping> {
         let vm_mapping = self.vm_mappings.remove(key)?;
         self.total_vm -= vm_mapping.map_size();
+        if vm_mapping.is_accounted() {
+            self.committed_vm -= vm_mapping.map_size();
+            overcommit::unacct_memory(vm_mapping.map_size());
+        }
+        self.free_regions.release(vm_mapping.range());
         Some(vm_mapping)
     }
 
+    /// Removes all `VmMapping`s from the `Vmar`.
+    ///
+    /// The caller should unmap the pages from the `VmSpace`.
+    fn clear(&mut self) {
+        self.vm_mappings.clear();
+        self.free_regions.reset();
+        self.total_vm = 0;
+        overcommit::unacct_memory(self.committed_vm);
+        self.committed_vm = 0;
+    }
+
     /// Calculates the total amount of overlap between `VmMapping`s
     /// and the provided range.
     fn count_overlap_size(&self, range: Range<Vaddr>) -> usize {
@@ -199,23 +315,36 @@ impl VmarInner {
         let range = offset..offset + size;
         let mut mappings_to_remove = Vec::new();
         for vm_mapping in self.vm_mappings.find(&range) {
+            // Keep the updates to the file before the pages are unmapped. This is done before
+            // removing any mappings, so that nothing is changed if it fails.
+            let intersected_range = get_intersected_range(&range, &vm_mapping.range());
+            vm_mapping.sync(vm_space, &intersected_range, false)?;
             mappings_to_remove.push(vm_mapping.map_to_addr());
         }
+        let has_mappings = !mappings_to_remove.is_empty();
 
         for vm_mapping_addr in mappings_to_remove {
             let vm_mapping = self.remove(&vm_mapping_addr).unwrap();
             let vm_mapping_range = vm_mapping.range();
             let intersected_range = get_intersected_range(&range, &vm_mapping_range);
 
-            let (left, taken, right) = vm_mapping.split_range(&intersected_range)?;
+            let (left, _taken, right) = vm_mapping.split_range(&intersected_range)?;
             if let Some(left) = left {
                 self.insert(left);
             }
             if let Some(right) = right {
                 self.insert(right);
             }
+        }
 
-            taken.unmap(vm_space)?;
+        if has_mappings {
+            // Unmap all the truncated mappings at once, so that the TLB entries of the range
+            // are flushed with a single shootdown.
+            let preempt_guard = disable_preempt();
+            let mut cursor = vm_space.cursor_mut(&preempt_guard, &range)?;
+            cursor.unmap(range.len());
+            cursor.flusher().dispatch_tlb_flush();
+            cursor.flusher().sync_tlb_flush();
         }
 
         Ok(offset..(offset + size))
@@ -225,43 +354,15 @@ impl VmarInner {
     ///
     /// If no such region is found, return an error.
     fn alloc_free_region(&mut self, size: usize, align: usize) -> Result<Range<Vaddr>> {
-        // Fast path that there's still room to the end.
-        let highest_occupied = self
-            .vm_mappings
-            .iter()
-            .next_back()
-            .map_or(ROOT_VMAR_LOWEST_ADDR, |vm_mapping| vm_mapping.range().end);
-        // FIXME: The up-align may overflow.
-        let last_occupied_aligned = highest_occupied.align_up(align);
-        if let Some(last) = last_occupied_aligned.checked_add(size) {
-            if last <= ROOT_VMAR_CAP_ADDR {
-                return Ok(last_occupied_aligned..last);
-            }
-        }
-
-        // Slow path that we need to search for a free region.
-        // Here, we use a simple brute-force FIRST-FIT algorithm.
-        // Allocate as low as possible to reduce fragmentation.
-        let mut last_end: Vaddr = ROOT_VMAR_LOWEST_ADDR;
-        for vm_mapping in self.vm_mappings.iter() {
-            let range = vm_mapping.range();
-
-            debug_assert!(range.start >= last_end);
-            debug_assert!(range.end <= highest_occupied);
-
-            let last_aligned = last_end.align_up(align);
-            let needed_end = last_aligned
-                .checked_add(size)
-                .ok_or(Error::new(Errno::ENOMEM))?;
-
-            if needed_end <= range.start {
-                return Ok(last_aligned..needed_end);
-            }
-
-            last_end = range.end;
-        }
+        self.free_regions.find(size, align).ok_or_else(|| {
+            Error::with_message(Errno::ENOMEM, "Cannot find free region for mapping")
+        })
+    }
+}
 
-        return_errno_with_message!(Errno::ENOMEM, "Cannot find free region for mapping");
+impl Drop for VmarInner {
+    fn drop(&mut self) {
+        overcommit::unacct_memory(self.committed_vm);
     }
 }
 
@@ -310,22 +411,37 @@ impl Vmar_ {
         let mut protect_mappings = Vec::new();
 
         for vm_mapping in inner.vm_mappings.find(&range) {
-            protect_mappings.push((vm_mapping.map_to_addr(), vm_mapping.perms()));
-        }
-
-        for (vm_mapping_addr, vm_mapping_perms) in protect_mappings {
-            if perms == vm_mapping_perms {
+            if perms == vm_mapping.perms() {
                 continue;
             }
-            let vm_mapping = inner.remove(&vm_mapping_addr).unwrap();
-            let vm_mapping_range = vm_mapping.range();
+            // A private mapping that becomes writable for the first time needs to be accounted.
+            let needs_acct = perms.contains(VmPerms::WRITE)
+                && !vm_mapping.perms().contains(VmPerms::WRITE)
+                && !vm_mapping.is_shared()
+                && !vm_mapping.is_accounted();
+            protect_mappings.push((vm_mapping.map_to_addr(), needs_acct));
+        }
+
+        for (vm_mapping_addr, needs_acct) in protect_mappings {
+            let vm_mapping_range = inner
+                .vm_mappings
+                .find_one(&vm_mapping_addr)
+                .unwrap()
+                .range();
             let intersected_range = get_intersected_range(&range, &vm_mapping_range);
+            if needs_acct {
+                overcommit::check_enough_memory(intersected_range.len())?;
+            }
 
             // Protects part of the taken `VmMapping`.
+            let vm_mapping = inner.remove(&vm_mapping_addr).unwrap();
             let (left, taken, right) = vm_mapping.split_range(&intersected_range)?;
 
-            let taken = taken.protect(vm_space.as_ref(), perms);
-            inner.insert(taken);
+            let mut taken = taken.protect(vm_space.as_ref(), perms);
+            if needs_acct {
+                taken = taken.set_accounted(true);
+            }
+            inner.insert_and_merge(taken);
 
             // And put the rest back.
             if let Some(left) = left {
@@ -339,8 +455,69 @@ impl Vmar_ {
         Ok(())
     }
 
+    /// Sets whether the private anonymous mappings within the range can be merged by KSM.
+    ///
+    /// Other mappings are skipped. If the range contains unmapped pages, this method returns
+    /// `Err` after updating the mapped part.
+    fn set_mergeable(self: &Arc<Self>, range: Range<Vaddr>, is_mergeable: bool) -> Result<()> {
+        let mut inner = self.inner.write();
+
+        let mut unmapped_start = range.start;
+        let mut has_hole = false;
+        let mut update_mappings = Vec::new();
+        for vm_mapping in inner.vm_mappings.find(&range) {
+            let vm_mapping_range = vm_mapping.range();
+            has_hole |= vm_mapping_range.start > unmapped_start;
+            unmapped_start = vm_mapping_range.end;
+
+            if vm_mapping.vmo().is_none()
+                && !vm_mapping.is_shared()
+                && vm_mapping.is_mergeable() != is_mergeable
+            {
+                update_mappings.push(vm_mapping.map_to_addr());
+            }
+        }
+
+        if is_mergeable && !update_mappings.is_empty() {
+            ksm::register(self);
+        }
+
+        for vm_mapping_addr in update_mappings {
+            let vm_mapping_range = inner
+                .vm_mappings
+                .find_one(&vm_mapping_addr)
+                .unwrap()
+                .range();
+            let intersected_range = get_intersected_range(&range, &vm_mapping_range);
+
+            // Copies the merged pages back to private pages before the mapping is changed, so
+            // that the mapping stays untouched on failures.
+            if !is_mergeable {
+                ksm::unmerge_range(&self.vm_space, &intersected_range)?;
+            }
+
+            let vm_mapping = inner.remove(&vm_mapping_addr).unwrap();
+            let (left, taken, right) = vm_mapping.split_range(&intersected_range)?;
+
+            inner.insert_and_merge(taken.set_mergeable(is_mergeable));
+            if let Some(left) = left {
+                inner.insert(left);
+            }
+            if let Some(right) = right {
+                inner.insert(right);
+            }
+        }
+
+        if has_hole || unmapped_start < range.end {
+            return_errno_with_message!(Errno::ENOMEM, "the range contains unmapped pages");
+        }
+        Ok(())
+    }
+
     /// Handles user space page fault, if the page fault is successfully handled, return Ok(()).
     pub fn handle_page_fault(&self, page_fault_info: &PageFaultInfo) -> Result<()> {
+        stat::count_vm_event(VmEvent::PgFault);
+
         let address = page_fault_info.address;
         if !(self.base..self.base + self.size).contains(&address) {
             return_errno_with_message!(Errno::EACCES, "page fault addr is not in current vmar");
@@ -359,7 +536,17 @@ impl Vmar_ {
     /// Clears all content of the root VMAR.
     fn clear_root_vmar(&self) -> Result<()> {
         let mut inner = self.inner.write();
-        inner.vm_mappings.clear();
+        for vm_mapping in inner.vm_mappings.iter() {
+            // The teardown must go on, so the updates that cannot be kept are lost.
+            if let Err(err) = vm_mapping.sync(&self.vm_space, &vm_mapping.range(), false) {
+                warn!(
+                    "failed to sync the mapping at {:#x}: {:?}",
+                    vm_mapping.map_to_addr(),
+                    err
+                );
+            }
+        }
+        inner.clear();
 
         // Keep `inner` locked to avoid race conditions.
         let preempt_guard = disable_preempt();
@@ -374,6 +561,154 @@ impl Vmar_ {
         Ok(())
     }
 
+    fn sync(&self, range: Range<Vaddr>, write_back: bool) -> Result<()> {
+        let inner = self.inner.read();
+
+        let mut unmapped_start = range.start;
+        let mut has_hole = false;
+        for vm_mapping in inner.vm_mappings.find(&range) {
+            let vm_mapping_range = vm_mapping.range();
+            has_hole |= vm_mapping_range.start > unmapped_start;
+            unmapped_start = vm_mapping_range.end;
+
+            let intersected_range = get_intersected_range(&range, &vm_mapping_range);
+            vm_mapping.sync(&self.vm_space, &intersected_range, write_back)?;
+        }
+
+        if has_hole || unmapped_start < range.end {
+            return_errno_with_message!(Errno::ENOMEM, "the range contains unmapped pages");
+        }
+        Ok(())
+    }
+
+    fn check_access(&self, range: Range<Vaddr>, perms: VmPerms) -> Result<()> {
+        let inner = self.inner.read();
+
+        let mut unmapped_start = range.start;
+        for vm_mapping in inner.vm_mappings.find(&range) {
+            let vm_mapping_range = vm_mapping.range();
+            if vm_mapping_range.start > unmapped_start {
+                break;
+            }
+            if !vm_mapping.perms().contains(perms) {
+                return_errno_with_message!(Errno::EFAULT, "the range is not accessible");
+            }
+            unmapped_start = vm_mapping_range.end;
+        }
+
+        if unmapped_start < range.end {
+            return_errno_with_message!(Errno::EFAULT, "the range contains unmapped pages");
+        }
+        Ok(())
+    }
+
+    fn write_bytes(&self, vaddr: Vaddr, buf: &[u8]) -> Result<()> {
+        let end = vaddr
+            .checked_add(buf.len())
+            .filter(|end| *end <= MAX_USERSPACE_VADDR)
+            .ok_or_else(|| {
+                Error::with_message(Errno::EFAULT, "the address is not in user space")
+            })?;
+
+        let mut addr = vaddr;
+        while addr < end {
+            let page_addr = addr.align_down(PAGE_SIZE);
+            let copy_len = (page_addr + PAGE_SIZE).min(end) - addr;
+            let src = &buf[addr - vaddr..][..copy_len];
+
+            if !self.try_write_page(page_addr, addr - page_addr, src) {
+                let page_fault_info = PageFaultInfo {
+                    address: addr,
+                    required_perms: VmPerms::WRITE,
+                };
+                self.handle_page_fault(&page_fault_info).map_err(|_| {
+                    Error::with_message(Errno::EFAULT, "the address is not writable")
+                })?;
+                // Retry after the page is faulted in.
+                continue;
+            }
+
+            addr += copy_len;
+        }
+
+        Ok(())
+    }
+
+    /// Writes bytes to a page if it is mapped as writable.
+    ///
+    /// Returns `false` without writing anything if the page needs to be
+    /// faulted in.
+    fn try_write_page(&self, page_addr: Vaddr, offset: usize, src: &[u8]) -> bool {
+        let preempt_guard = disable_preempt();
+        let page_range = page_addr..page_addr + PAGE_SIZE;
+        let Ok(mut cursor) = self.vm_space.cursor(&preempt_guard, &page_range) else {
+            return false;
+        };
+        let Ok(VmItem::Mapped { frame, prop, .. }) = cursor.query() else {
+            return false;
+        };
+        if !prop.flags.contains(PageFlags::W) {
+            return false;
+        }
+
+        let mut writer = frame.writer();
+        writer.skip(offset);
+        writer.write(&mut VmReader::from(src));
+        true
+    }
+
+    fn read_bytes(&self, vaddr: Vaddr, buf: &mut [u8]) -> Result<()> {
+        let end = vaddr
+            .checked_add(buf.len())
+            .filter(|end| *end <= MAX_USERSPACE_VADDR)
+            .ok_or_else(|| {
+                Error::with_message(Errno::EFAULT, "the address is not in user space")
+            })?;
+
+        let mut addr = vaddr;
+        while addr < end {
+            let page_addr = addr.align_down(PAGE_SIZE);
+            let copy_len = (page_addr + PAGE_SIZE).min(end) - addr;
+            let dst = &mut buf[addr - vaddr..][..copy_len];
+
+            if !self.try_read_page(page_addr, addr - page_addr, dst) {
+                let page_fault_info = PageFaultInfo {
+                    address: addr,
+                    required_perms: VmPerms::READ,
+                };
+                self.handle_page_fault(&page_fault_info).map_err(|_| {
+                    Error::with_message(Errno::EFAULT, "the address is not readable")
+                })?;
+                // Retry after the page is faulted in.
+                continue;
+            }
+
+            addr += copy_len;
+        }
+
+        Ok(())
+    }
+
+    /// Reads bytes from a page if it is mapped.
+    ///
+    /// Returns `false` without reading anything if the page needs to be
+    /// faulted in.
+    fn try_read_page(&self, page_addr: Vaddr, offset: usize, dst: &mut [u8]) -> bool {
+        let preempt_guard = disable_preempt();
+        let page_range = page_addr..page_addr + PAGE_SIZE;
+        let Ok(mut cursor) = self.vm_space.cursor(&preempt_guard, &page_range) else {
+            return false;
+        };
+        let Ok(VmItem::Mapped { frame, .. }) = cursor.query() else {
+            return false;
+        };
+
+        let mut reader = frame.reader();
+        reader.skip(offset);
+        reader.read(&mut VmWriter::from(dst));
+        true
+    }
+
     pub fn remove_mapping(&self, range: Range<usize>) -> Result<()> {
         let mut inner = self.inner.write();
         inner.alloc_free_region_exact_truncate(&self.vm_space, range.start, range.len())?;
@@ -407,13 +742,17 @@ impl Vmar_ {
         let last_mapping = inner.vm_mappings.find_one(&(old_map_end - 1)).unwrap();
         let last_mapping_addr = last_mapping.map_to_addr();
         let extra_mapping_start = last_mapping.map_end();
+        let is_accounted = last_mapping.is_accounted();
 
         inner.check_expand_size(new_map_end - extra_mapping_start)?;
+        if is_accounted {
+            overcommit::check_enough_memory(new_map_end - extra_mapping_start)?;
+        }
 
         let last_mapping = inner.remove(&last_mapping_addr).unwrap();
         inner.alloc_free_region_exact(extra_mapping_start, new_map_end - extra_mapping_start)?;
         let last_mapping = last_mapping.enlarge(new_map_end - extra_mapping_start);
-        inner.insert(last_mapping);
+        inner.insert_and_merge(last_mapping);
         Ok(())
     }
 
@@ -431,7 +770,14 @@ impl Vmar_ {
 
         {
             let inner = self.inner.read();
+            // The private writable mappings are duplicated in the child, so they are committed
+            // again.
+            overcommit::check_enough_memory(inner.committed_vm)?;
+
             let mut new_inner = new_vmar_.inner.write();
+            new_inner
+                .free_regions
+                .set_reserved(inner.free_regions.reserved());
 
             // Clone mappings.
             let preempt_guard = disable_preempt();
@@ -447,17 +793,29 @@ impl Vmar_ {
                 let new_mapping = vm_mapping.new_fork()?;
                 new_inner.insert(new_mapping);
 
-                // Protect the mapping and copy to the new page table for COW.
+                // Protect the mapping and copy to the new page table for COW. Pages in shared
+                // mappings are shared by both processes, so they need no protection.
                 cur_cursor.jump(base).unwrap();
                 new_cursor.jump(base).unwrap();
+                let is_shared = vm_mapping.is_shared();
                 let mut op = |page: &mut PageProperty| {
-                    page.flags -= PageFlags::W;
+                    if !is_shared {
+                        page.flags -= PageFlags::W;
+                    }
                 };
                 new_cursor.copy_from(&mut cur_cursor, vm_mapping.map_size(), &mut op);
             }
             cur_cursor.flusher().issue_tlb_flush(TlbFlushOp::All);
             cur_cursor.flusher().dispatch_tlb_flush();
             cur_cursor.flusher().sync_tlb_flush();
+            drop(new_cursor);
+            drop(cur_cursor);
+            drop(preempt_guard);
+
+            // The child inherits the mergeable mappings, so it should be scanned by KSM as well.
+            if inner.vm_mappings.iter().any(VmMapping::is_mergeable) {
+                ksm::register(&new_vmar_);
+            }
         }
 
         Ok(new_vmar_)
@@ -476,14 +834,192 @@ impl<R> Vmar<R> {
     pub fn size(&self) -> usize {
         self.0.size
     }
+
+    /// Returns the total size of the mappings in the VMAR in bytes.
+    pub fn total_vm(&self) -> usize {
+        self.0.inner.read().total_vm
+    }
+
+    /// Returns the number of the pages that are mapped in the page table.
+    ///
+    /// This is the resident set size (RSS) in pages. The pages are counted by walking the page
+    /// table, so this method should not be called on hot paths.
+    pub fn resident_pages(&self) -> usize {
+        let inner = self.0.inner.read();
+        inner
+            .vm_mappings
+            .iter()
+            .map(|vm_mapping| {
+                let preempt_guard = disable_preempt();
+                let Ok(cursor) = self.0.vm_space.cursor(&preempt_guard, &vm_mapping.range()) else {
+                    return 0;
+                };
+                cursor
+                    .filter(|item| matches!(item, VmItem::Mapped { .. }))
+                    .count()
+            })
+            .sum()
+    }
+
+    /// Returns the information of all the mappings, sorted by their addresses.
+    pub fn mappings_info(&self) -> Vec<VmMappingInfo> {
+        let inner = self.0.inner.read();
+        inner
+            .vm_mappings
+            .iter()
+            .map(|vm_mapping| VmMappingInfo {
+                range: vm_mapping.range(),
+                perms: vm_mapping.perms(),
+                is_shared: vm_mapping.is_shared(),
+                vmo_offset: vm_mapping.vmo_offset().unwrap_or(0),
+                mapped_file: vm_mapping.mapped_file().cloned(),
+            })
+            .collect()
+    }
+
+    /// Returns the memory usage of all the mappings, which is reported in
+    /// `/proc/[pid]/smaps_rollup`.
+    ///
+    /// Like [`Self::resident_pages`], the pages are counted by walking the page table, so this
+    /// method should not be called on hot paths.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let inner = self.0.inner.read();
+        let mut usage = MemoryUsage::default();
+        // The proportional sizes are accumulated with extra precision bits, like Linux.
+        let mut pss = [0u64; 4];
+
+        for vm_mapping in inner.vm_mappings.iter() {
+            let preempt_guard = disable_preempt();
+            let Ok(cursor) = self.0.vm_space.cursor(&preempt_guard, &vm_mapping.range()) else {
+                continue;
+            };
+
+            let is_shmem = vm_mapping.is_shared() && vm_mapping.mapped_file().is_none();
+            for item in cursor {
+                let VmItem::Mapped { frame, prop, .. } = item else {
+                    continue;
+                };
+
+                // A private mapping maps the pages of its VMO until they are copied on write, in
+                // which case the pages are dirty.
+                let is_anon = !vm_mapping.is_shared()
+                    && (vm_mapping.vmo().is_none() || prop.flags.contains(PageFlags::DIRTY));
+                let is_dirty = prop.flags.contains(PageFlags::DIRTY);
+
+                // There is no reverse mapping to count the mappers of a page, so the mappers are
+                // estimated by excluding the other known references to the page, i.e., the
+                // `frame` handle itself, the references from the VMO (and the page cache), and
+                // the reference from KSM.
+                let mut nr_other_refs = 1;
+                if let Some(vmo) = vm_mapping.vmo()
+                    && !is_anon
+                {
+                    nr_other_refs += if vmo.has_pager() { 2 } else { 1 };
+                }
+                if ksm::is_ksm_frame(&frame) {
+                    nr_other_refs += 1;
+                }
+                let nr_mappers = frame.reference_count().saturating_sub(nr_other_refs).max(1);
+
+                usage.rss += PAGE_SIZE;
+                let page_pss = ((PAGE_SIZE as u64) << PSS_SHIFT) / nr_mappers;
+                pss[0] += page_pss;
+                if is_anon {
+                    usage.anonymous += PAGE_SIZE;
+                    pss[1] += page_pss;
+                } else if is_shmem {
+                    pss[3] += page_pss;
+                } else {
+                    pss[2] += page_pss;
+                }
+
+                match (nr_mappers > 1, is_dirty) {
+                    (true, false) => usage.shared_clean += PAGE_SIZE,
+                    (true, true) => usage.shared_dirty += PAGE_SIZE,
+                    (false, false) => usage.private_clean += PAGE_SIZE,
+                    (false, true) => usage.private_dirty += PAGE_SIZE,
+                }
+                if prop.flags.contains(PageFlags::ACCESSED) {
+                    usage.referenced += PAGE_SIZE;
+                }
+            }
+        }
+
+        usage.pss = (pss[0] >> PSS_SHIFT) as usize;
+        usage.pss_anon = (pss[1] >> PSS_SHIFT) as usize;
+        usage.pss_file = (pss[2] >> PSS_SHIFT) as usize;
+        usage.pss_shmem = (pss[3] >> PSS_SHIFT) as usize;
+        usage
+    }
+
+    /// Returns the VMO mapped by the mapping that contains the address, together with the range
+    /// of the mapping and the offset of the mapping in the VMO.
+    ///
+    /// This method returns `None` if there is no mapping at the address or the mapping is
+    /// anonymous.
+    pub fn query_vmo(&self, addr: Vaddr) -> Option<(Range<Vaddr>, Vmo, usize)> {
+        let inner = self.0.inner.read();
+        let vm_mapping = inner.vm_mappings.find_one(&addr)?;
+        let vmo = vm_mapping.vmo()?.dup().ok()?;
+        Some((vm_mapping.range(), vmo, vm_mapping.vmo_offset().unwrap()))
+    }
+}
+
+/// The information of a mapping in a VMAR.
+#[derive(Debug, Clone)]
+pub struct VmMappingInfo {
+    /// The range of the virtual addresses.
+    pub range: Range<Vaddr>,
+    /// The permissions of the pages.
+    pub perms: VmPerms,
+    /// Whether the mapping is shared.
+    pub is_shared: bool,
+    /// The offset of the mapping in the VMO in bytes, or zero for anonymous mappings.
+    pub vmo_offset: usize,
+    /// The mapped file, or `None` if the mapping is not backed by a file.
+    pub mapped_file: Option<Dentry>,
+}
+
+/// The memory usage of the mappings in a VMAR, in bytes.
+///
+/// The proportional set size (PSS) of a page is its size divided by the number
+/// of the processes that map it.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryUsage {
+    /// The resident set size.
+    pub rss: usize,
+    /// The proportional set size.
+    pub pss: usize,
+    /// The proportional set size of the anonymous pages.
+    pub pss_anon: usize,
+    /// The proportional set size of the file pages.
+    pub pss_file: usize,
+    /// The proportional set size of the shared anonymous pages.
+    pub pss_shmem: usize,
+    /// The clean pages that are mapped by other processes as well.
+    pub shared_clean: usize,
+    /// The dirty pages that are mapped by other processes as well.
+    pub shared_dirty: usize,
+    /// The clean pages that are only mapped by this process.
+    pub private_clean: usize,
+    /// The dirty pages that are only mapped by this process.
+    pub private_dirty: usize,
+    /// The pages that have been accessed.
+    pub referenced: usize,
+    /// The anonymous pages.
+    pub anonymous: usize,
 }
 
+/// The extra precision bits of the accumulated proportional set sizes.
+const PSS_SHIFT: u32 = 12;
+
 /// Options for creating a new mapping. The mapping is not allowed to overlap
 /// with any child VMARs. And unless specified otherwise, it is not allowed
 /// to overlap with any existing mapping, either.
 pub struct VmarMapOptions<'a, R1, R2> {
     parent: &'a Vmar<R1>,
     vmo: Option<Vmo<R2>>,
+    mapped_file: Option<Dentry>,
     perms: VmPerms,
     vmo_offset: usize,
     vmo_limit: usize,
@@ -495,6 +1031,8 @@ pub struct VmarMapOptions<'a, R1, R2> {
     is_shared: bool,
     // Whether the mapping needs to handle surrounding pages when handling page fault.
     handle_page_faults_around: bool,
+    // Whether the mapping is mapped with `MAP_NORESERVE`
+    no_reserve: bool,
 }
 
 impl<'a, R1, R2> VmarMapOptions<'a, R1, R2> {
@@ -508,6 +1046,7 @@ impl<'a, R1, R2> VmarMapOptions<'a, R1, R2> {
         Self {
             parent,
             vmo: None,
+            mapped_file: None,
             perms,
             vmo_offset: 0,
             vmo_limit: usize::MAX,
@@ -517,6 +1056,7 @@ impl<'a, R1, R2> VmarMapOptions<'a, R1, R2> {
             can_overwrite: false,
             is_shared: false,
             handle_page_faults_around: false,
+            no_reserve: false,
         }
     }
 
@@ -541,6 +1081,15 @@ impl<'a, R1, R2> VmarMapOptions<'a, R1, R2> {
         self
     }
 
+    /// Sets the file that the mapping maps.
+    ///
+    /// The file is only recorded for reporting, e.g., in `/proc/[pid]/map_files`. The content
+    /// of the mapping is still provided by the VMO.
+    pub fn mapped_file(mut self, mapped_file: Dentry) -> Self {
+        self.mapped_file = Some(mapped_file);
+        self
+    }
+
     /// Sets the offset of the first memory page in the VMO that is to be
     /// mapped into the VMAR.
     ///
@@ -608,6 +1157,15 @@ impl<'a, R1, R2> VmarMapOptions<'a, R1, R2> {
         self.handle_page_faults_around = true;
         self
     }
+
+    /// Sets the mapping not to reserve memory for its pages.
+    ///
+    /// A private writable mapping is not accounted as committed memory if this option is set,
+    /// unless the overcommit mode is [`overcommit::OvercommitMode::Never`].
+    pub fn no_reserve(mut self) -> Self {
+        self.no_reserve = true;
+        self
+    }
 }
 
 impl<'a, R1, R2> VmarMapOptions<'a, R1, R2>
@@ -624,6 +1182,7 @@ where
         let Self {
             parent,
             vmo,
+            mapped_file,
             perms,
             vmo_offset,
             vmo_limit,
@@ -633,6 +1192,7 @@ where
             can_overwrite,
             is_shared,
             handle_page_faults_around,
+            no_reserve,
         } = self;
 
         let mut inner = parent.0.inner.write();
@@ -651,6 +1211,13 @@ where
             }
         })?;
 
+        let is_accounted = !is_shared
+            && perms.contains(VmPerms::WRITE)
+            && (!no_reserve || overcommit::overcommit_memory() == OvercommitMode::Never);
+        if is_accounted {
+            overcommit::check_enough_memory(map_size)?;
+        }
+
         // Allocates a free region.
         trace!("allocate free region, map_size = 0x{:x}, offset = {:x?}, align = 0x{:x}, can_overwrite = {}", map_size, offset, align, can_overwrite);
         let map_to_addr = if can_overwrite {
@@ -675,13 +1242,15 @@ where
             NonZeroUsize::new(map_size).unwrap(),
             map_to_addr,
             vmo,
+            mapped_file,
             is_shared,
             handle_page_faults_around,
             perms,
-        );
+        )
+        .set_accounted(is_accounted);
 
         // Add the mapping to the VMAR.
-        inner.insert(vm_mapping);
+        inner.insert_and_merge(vm_mapping);
 
         Ok(map_to_addr)
     }
@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use core::{
+    cell::Cell,
     cmp::{max, min},
     num::NonZeroUsize,
     ops::Range,
@@ -9,19 +10,20 @@ use core::{
 use align_ext::AlignExt;
 use ostd::{
     mm::{
-        tlb::TlbFlushOp, vm_space::VmItem, CachePolicy, FrameAllocOptions, PageFlags, PageProperty,
-        UFrame, VmSpace,
+        tlb::TlbFlushOp, vm_space::VmItem, CachePolicy, PageFlags, PageProperty, UFrame, VmSpace,
     },
     task::disable_preempt,
 };
 
-use super::interval_set::Interval;
+use super::{interval_set::Interval, ksm::is_ksm_frame};
 use crate::{
+    fs::path::Dentry,
     prelude::*,
     thread::exception::PageFaultInfo,
     vm::{
         perms::VmPerms,
-        util::duplicate_frame,
+        stat::{self, VmEvent},
+        util::{alloc_user_frame, duplicate_frame},
         vmo::{CommitFlags, Vmo, VmoCommitError},
     },
 };
@@ -41,7 +43,8 @@ use crate::{
 /// Such mappings will also be VMO-backed mappings.
 ///
 /// This type controls the actual mapping in the [`VmSpace`]. It is a linear
-/// type and cannot be [`Drop`]. To remove a mapping, use [`Self::unmap`].
+/// type and cannot be [`Drop`]. To remove a mapping, unmap its range from the
+/// [`VmSpace`] before dropping it.
 #[derive(Debug)]
 pub(super) struct VmMapping {
     /// The size of mapping, in bytes. The map size can even be larger than the
@@ -57,6 +60,8 @@ pub(super) struct VmMapping {
     /// The start of the virtual address maps to the start of the range
     /// specified in [`MappedVmo`].
     vmo: Option<MappedVmo>,
+    /// The file that the mapping maps, or `None` if the mapping is not backed by a file.
+    mapped_file: Option<Dentry>,
     /// Whether the mapping is shared.
     ///
     /// The updates to a shared mapping are visible among processes, or carried
@@ -69,6 +74,15 @@ pub(super) struct VmMapping {
     ///
     /// All pages within the same `VmMapping` have the same permissions.
     perms: VmPerms,
+    /// Whether the pages in the mapping can be merged with identical pages by KSM.
+    ///
+    /// This is set by `madvise(MADV_MERGEABLE)` and only takes effect on private anonymous
+    /// mappings.
+    is_mergeable: bool,
+    /// Whether the mapping is accounted as committed memory.
+    ///
+    /// See [`crate::vm::overcommit`] for which mappings are accounted.
+    is_accounted: bool,
 }
 
 impl Interval<Vaddr> for VmMapping {
@@ -84,6 +98,7 @@ impl VmMapping {
         map_size: NonZeroUsize,
         map_to_addr: Vaddr,
         vmo: Option<MappedVmo>,
+        mapped_file: Option<Dentry>,
         is_shared: bool,
         handle_page_faults_around: bool,
         perms: VmPerms,
@@ -92,15 +107,19 @@ impl VmMapping {
             map_size,
             map_to_addr,
             vmo,
+            mapped_file,
             is_shared,
             handle_page_faults_around,
             perms,
+            is_mergeable: false,
+            is_accounted: false,
         }
     }
 
     pub(super) fn new_fork(&self) -> Result<VmMapping> {
         Ok(VmMapping {
             vmo: self.vmo.as_ref().map(|vmo| vmo.dup()).transpose()?,
+            mapped_file: self.mapped_file.clone(),
             ..*self
         })
     }
@@ -124,6 +143,36 @@ impl VmMapping {
     pub fn perms(&self) -> VmPerms {
         self.perms
     }
+
+    /// Returns whether the mapping is shared.
+    pub fn is_shared(&self) -> bool {
+        self.is_shared
+    }
+
+    /// Returns whether the pages in the mapping can be merged by KSM.
+    pub fn is_mergeable(&self) -> bool {
+        self.is_mergeable
+    }
+
+    /// Returns whether the mapping is accounted as committed memory.
+    pub fn is_accounted(&self) -> bool {
+        self.is_accounted
+    }
+
+    /// Returns the offset of the mapping in the VMO, or `None` if the mapping is anonymous.
+    pub fn vmo_offset(&self) -> Option<usize> {
+        self.vmo.as_ref().map(|vmo| vmo.range.start)
+    }
+
+    /// Returns the mapped VMO, or `None` if the mapping is anonymous.
+    pub fn vmo(&self) -> Option<&Vmo> {
+        self.vmo.as_ref().map(|vmo| &vmo.vmo)
+    }
+
+    /// Returns the mapped file, or `None` if the mapping is not backed by a file.
+    pub fn mapped_file(&self) -> Option<&Dentry> {
+        self.mapped_file.as_ref()
+    }
 }
 
 /****************************** Page faults **********************************/
@@ -200,7 +249,11 @@ impl VmMapping {
                     // frame. We can directly map the frame as writable without
                     // copying. In this case, the reference count of the frame is 2 (
                     // one for the mapping and one for the frame handle itself).
-                    let only_reference = frame.reference_count() == 2;
+                    //
+                    // A KSM page is also referenced by the stable table of KSM, so it is
+                    // never written in place.
+                    let is_ksm = is_ksm_frame(&frame);
+                    let only_reference = frame.reference_count() == 2 && !is_ksm;
 
                     let new_flags = PageFlags::W | PageFlags::ACCESSED | PageFlags::DIRTY;
 
@@ -211,7 +264,10 @@ impl VmMapping {
                     } else {
                         let new_frame = duplicate_frame(&frame)?;
                         prop.flags |= new_flags;
-                        cursor.map(new_frame.into(), prop);
+                        cursor.map(new_frame, prop);
+                        if is_ksm {
+                            stat::count_vm_event(VmEvent::CowKsm);
+                        }
                     }
                     cursor.flusher().sync_tlb_flush();
                 }
@@ -227,6 +283,7 @@ impl VmMapping {
                                 .as_ref()
                                 .unwrap()
                                 .commit_on(index, CommitFlags::empty())?;
+                            stat::count_vm_event(VmEvent::PgMajFault);
                             continue 'retry;
                         }
                     };
@@ -252,6 +309,18 @@ impl VmMapping {
             }
             break 'retry;
         }
+
+        // The write access to a shared mapping dirties the page. The VMO is notified after
+        // the page table is unlocked, since the pager may sleep.
+        if is_write && self.is_shared {
+            if let Some(vmo) = &self.vmo {
+                let page_offset = page_aligned_addr - self.map_to_addr;
+                if page_offset < vmo.size() {
+                    vmo.mark_page_dirty(page_offset)?;
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -262,25 +331,28 @@ impl VmMapping {
     ) -> core::result::Result<(UFrame, bool), VmoCommitError> {
         let mut is_readonly = false;
         let Some(vmo) = &self.vmo else {
-            return Ok((FrameAllocOptions::new().alloc_frame()?.into(), is_readonly));
+            return Ok((alloc_user_frame(true)?, is_readonly));
         };
 
         let page_offset = page_fault_addr.align_down(PAGE_SIZE) - self.map_to_addr;
         if !self.is_shared && page_offset >= vmo.size() {
             // The page index is outside the VMO. This is only allowed in private mapping.
-            return Ok((FrameAllocOptions::new().alloc_frame()?.into(), is_readonly));
+            return Ok((alloc_user_frame(true)?, is_readonly));
         }
 
         let page = vmo.get_committed_frame(page_offset)?;
         if !self.is_shared && write {
             // Write access to private VMO-backed mapping. Performs COW directly.
-            Ok((duplicate_frame(&page)?.into(), is_readonly))
+            Ok((duplicate_frame(&page)?, is_readonly))
         } else {
             // Operations to shared mapping or read access to private VMO-backed mapping.
             // If read access to private VMO-backed mapping triggers a page fault,
             // the map should be readonly. If user next tries to write to the frame,
             // another page fault will be triggered which will performs a COW (Copy-On-Write).
-            is_readonly = !self.is_shared;
+            //
+            // Read access to a shared mapping of a file also maps the page as readonly,
+            // so that the next write access triggers a page fault that marks the page dirty.
+            is_readonly = !self.is_shared || (!write && vmo.has_pager());
             Ok((page, is_readonly))
         }
     }
@@ -330,6 +402,11 @@ impl VmMapping {
                 Err(VmoCommitError::NeedIo(index)) => {
                     drop(preempt_guard);
                     vmo.commit_on(index, CommitFlags::empty())?;
+                    // Only the I/O for the faulting page makes the page fault a major one.
+                    if index * PAGE_SIZE + self.map_to_addr == page_fault_addr.align_down(PAGE_SIZE)
+                    {
+                        stat::count_vm_event(VmEvent::PgMajFault);
+                    }
                     start_addr = index * PAGE_SIZE + self.map_to_addr;
                     continue 'retry;
                 }
@@ -350,6 +427,50 @@ impl VmMapping {
         }
     }
 
+    /// Sets whether the pages in the mapping can be merged by KSM.
+    ///
+    /// The caller should break the merged pages in the mapping if it becomes unmergeable.
+    pub(super) fn set_mergeable(self, is_mergeable: bool) -> Self {
+        Self {
+            is_mergeable,
+            ..self
+        }
+    }
+
+    /// Sets whether the mapping is accounted as committed memory.
+    ///
+    /// The caller should update the committed memory accordingly.
+    pub(super) fn set_accounted(self, is_accounted: bool) -> Self {
+        Self {
+            is_accounted,
+            ..self
+        }
+    }
+
+    /// Returns whether the mapping can be merged with the `next` mapping.
+    ///
+    /// Only adjacent private anonymous mappings with the same attributes can
+    /// be merged, because their pages live only in the page table.
+    pub(super) fn can_merge_with(&self, next: &VmMapping) -> bool {
+        self.map_end() == next.map_to_addr
+            && self.vmo.is_none()
+            && next.vmo.is_none()
+            && !self.is_shared
+            && !next.is_shared
+            && self.handle_page_faults_around == next.handle_page_faults_around
+            && self.perms == next.perms
+            && self.is_mergeable == next.is_mergeable
+            && self.is_accounted == next.is_accounted
+    }
+
+    /// Merges the mapping with the `next` mapping.
+    ///
+    /// The caller must ensure that [`Self::can_merge_with`] returns `true`.
+    pub(super) fn merge(self, next: VmMapping) -> Self {
+        debug_assert!(self.can_merge_with(&next));
+        self.enlarge(next.map_size())
+    }
+
     /// Splits the mapping at the specified address.
     ///
     /// The address must be within the mapping and page-aligned. The address
@@ -376,6 +497,7 @@ impl VmMapping {
             map_to_addr: self.map_to_addr,
             map_size: NonZeroUsize::new(left_size).unwrap(),
             vmo: l_vmo,
+            mapped_file: self.mapped_file.clone(),
             ..self
         };
         let right = Self {
@@ -429,26 +551,24 @@ impl VmMapping {
 /************************** VM Space operations ******************************/
 
 impl VmMapping {
-    /// Unmaps the mapping from the VM space.
-    pub(super) fn unmap(self, vm_space: &VmSpace) -> Result<()> {
-        let preempt_guard = disable_preempt();
-        let range = self.range();
-        let mut cursor = vm_space.cursor_mut(&preempt_guard, &range)?;
-
-        cursor.unmap(range.len());
-        cursor.flusher().dispatch_tlb_flush();
-        cursor.flusher().sync_tlb_flush();
-
-        Ok(())
-    }
-
     /// Change the perms of the mapping.
     pub(super) fn protect(self, vm_space: &VmSpace, perms: VmPerms) -> Self {
         let preempt_guard = disable_preempt();
         let range = self.range();
         let mut cursor = vm_space.cursor_mut(&preempt_guard, &range).unwrap();
 
-        let op = |p: &mut PageProperty| p.flags = perms.into();
+        let op = |p: &mut PageProperty| {
+            let was_writable = p.flags.contains(PageFlags::W);
+            let dirty_flags = p.flags & (PageFlags::ACCESSED | PageFlags::DIRTY);
+            p.flags = PageFlags::from(perms) | dirty_flags;
+            // A read-only page in a private mapping may be shared with the page cache or with
+            // other processes. It must stay read-only so that the next write access triggers COW.
+            // Likewise, a read-only page in a shared mapping may be clean. The next write access
+            // should trigger a page fault to mark it dirty.
+            if !was_writable {
+                p.flags -= PageFlags::W;
+            }
+        };
         while cursor.virt_addr() < range.end {
             if let Some(va) = cursor.protect_next(range.end - cursor.virt_addr(), op) {
                 cursor.flusher().issue_tlb_flush(TlbFlushOp::Range(va));
@@ -463,6 +583,72 @@ impl VmMapping {
     }
 }
 
+/******************************* Writeback ***********************************/
+
+impl VmMapping {
+    /// Synchronizes the pages within `range` that are written through the mapping with
+    /// the mapped VMO.
+    ///
+    /// This only takes effect on shared mappings of VMOs with pagers, i.e., files. The
+    /// dirty pages in the page table are marked dirty in the VMO and become read-only, so
+    /// that the next write access marks them dirty again. If `write_back` is true, the
+    /// dirty pages are then written back to the file.
+    pub(super) fn sync(
+        &self,
+        vm_space: &VmSpace,
+        range: &Range<Vaddr>,
+        write_back: bool,
+    ) -> Result<()> {
+        let Some(vmo) = &self.vmo else {
+            return Ok(());
+        };
+        if !self.is_shared || !vmo.has_pager() {
+            return Ok(());
+        }
+        debug_assert!(self.map_to_addr <= range.start && range.end <= self.map_end());
+
+        let mut dirty_offsets = Vec::new();
+        {
+            let preempt_guard = disable_preempt();
+            let mut cursor = vm_space.cursor_mut(&preempt_guard, range)?;
+
+            let old_flags = Cell::new(PageFlags::empty());
+            let op = |p: &mut PageProperty| {
+                old_flags.set(p.flags);
+                p.flags -= PageFlags::W | PageFlags::DIRTY;
+            };
+            while cursor.virt_addr() < range.end {
+                let Some(va) = cursor.protect_next(range.end - cursor.virt_addr(), op) else {
+                    break;
+                };
+                if old_flags.get().contains(PageFlags::DIRTY) {
+                    dirty_offsets.push(va.start - self.map_to_addr);
+                }
+                if old_flags.get().intersects(PageFlags::W | PageFlags::DIRTY) {
+                    cursor.flusher().issue_tlb_flush(TlbFlushOp::Range(va));
+                }
+            }
+            cursor.flusher().dispatch_tlb_flush();
+            cursor.flusher().sync_tlb_flush();
+        }
+
+        // The pager may sleep, so the VMO is notified after the page table is unlocked.
+        for page_offset in dirty_offsets {
+            vmo.mark_page_dirty(page_offset)?;
+        }
+
+        if write_back {
+            let start = range.start - self.map_to_addr;
+            let end = (range.end - self.map_to_addr).min(vmo.size());
+            if start < end {
+                vmo.write_back(&(start..end))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// A wrapper that represents a mapped [`Vmo`] and provide required functionalities
 /// that need to be provided to mappings from the VMO.
 #[derive(Debug)]
@@ -529,6 +715,25 @@ impl MappedVmo {
         self.vmo.try_operate_on_range(&range, operate)
     }
 
+    /// Returns whether the mapped VMO is backed by a pager.
+    fn has_pager(&self) -> bool {
+        self.vmo.has_pager()
+    }
+
+    /// Marks the page at the offset in the mapped VMO as dirty.
+    fn mark_page_dirty(&self, page_offset: usize) -> Result<()> {
+        debug_assert!(page_offset % PAGE_SIZE == 0);
+        self.vmo
+            .mark_page_dirty((self.range.start + page_offset) / PAGE_SIZE)
+    }
+
+    /// Writes back the dirty pages within the range in the mapped VMO.
+    fn write_back(&self, range: &Range<usize>) -> Result<()> {
+        debug_assert!(range.end <= self.range.len());
+        self.vmo
+            .write_back(self.range.start + range.start..self.range.start + range.end)
+    }
+
     /// Duplicates the capability.
     pub fn dup(&self) -> Result<Self> {
         Ok(Self {
@@ -13,12 +13,12 @@ use core::{
 use align_ext::AlignExt;
 use aster_rights::Rights;
 use ostd::{
-    mm::{FrameAllocOptions, UFrame, UntypedMem, VmReader, VmWriter},
+    mm::{UFrame, UntypedMem, VmReader, VmWriter},
     task::disable_preempt,
 };
 use xarray::{Cursor, LockedXArray, XArray};
 
-use crate::prelude::*;
+use crate::{prelude::*, vm::util::alloc_user_frame};
 
 mod dyn_cap;
 mod options;
@@ -187,7 +187,7 @@ impl Vmo_ {
     /// This operation may involve I/O operations if the VMO is backed by a pager.
     fn prepare_page(&self, page_idx: usize, commit_flags: CommitFlags) -> Result<UFrame> {
         match &self.pager {
-            None => Ok(FrameAllocOptions::new().alloc_frame()?.into()),
+            None => alloc_user_frame(true),
             Some(pager) => {
                 if commit_flags.will_overwrite() {
                     pager.commit_overwrite(page_idx)
@@ -334,6 +334,65 @@ impl Vmo_ {
         Ok(())
     }
 
+    /// Decommits the clean pages in the range, which the pager can provide again without losing
+    /// any data.
+    ///
+    /// The dirty pages and the pages that are mapped or in use are kept.
+    pub fn decommit_clean(&self, range: Range<usize>) -> Result<()> {
+        let Some(pager) = &self.pager else {
+            return Ok(());
+        };
+
+        let mut locked_pages = self.pages.lock();
+        if range.end > self.size() {
+            return_errno_with_message!(Errno::EINVAL, "operated range exceeds the vmo size");
+        }
+
+        let page_idx_range = get_page_idx_range(&range);
+        let mut cursor = locked_pages.cursor_mut(page_idx_range.start as u64);
+        let mut removed_page_idx = Vec::new();
+        for page_idx in page_idx_range {
+            if cursor.load().is_some_and(|page| pager.is_page_clean(&page)) {
+                cursor.remove();
+                removed_page_idx.push(page_idx);
+            }
+            cursor.next();
+        }
+
+        drop(cursor);
+        drop(locked_pages);
+
+        for page_idx in removed_page_idx {
+            pager.decommit_page(page_idx)?;
+        }
+
+        Ok(())
+    }
+
+    /// Marks the page at the index as updated, so that the pager will write it back.
+    ///
+    /// This is used for the updates that do not go through [`Self::write`], e.g., the
+    /// writes to the pages mapped by shared mappings.
+    pub fn mark_page_dirty(&self, page_idx: usize) -> Result<()> {
+        if let Some(pager) = &self.pager {
+            pager.update_page(page_idx)?;
+        }
+        Ok(())
+    }
+
+    /// Writes back the dirty pages within the specified range (in bytes) to the pager.
+    pub fn write_back(&self, range: Range<usize>) -> Result<()> {
+        if let Some(pager) = &self.pager {
+            pager.write_back(get_page_idx_range(&range))?;
+        }
+        Ok(())
+    }
+
+    /// Returns whether the VMO is attached to a pager.
+    pub fn has_pager(&self) -> bool {
+        self.pager.is_some()
+    }
+
     /// Reads the specified amount of buffer content starting from the target offset in the VMO.
     pub fn read(&self, offset: usize, writer: &mut VmWriter) -> Result<()> {
         let read_len = writer.avail().min(self.size().saturating_sub(offset));
@@ -485,16 +544,46 @@ impl Vmo_ {
     }
 }
 
+impl<R> PartialEq for Vmo<R> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
 impl<R> Vmo<R> {
     /// Returns the size (in bytes) of a VMO.
     pub fn size(&self) -> usize {
         self.0.size()
     }
 
+    /// Returns the number of the capabilities that refer to the same VMO, including this one.
+    ///
+    /// Each mapping of the VMO holds a capability, so this can be used to count the mappings.
+    pub fn num_handles(&self) -> usize {
+        Arc::strong_count(&self.0)
+    }
+
     /// Returns the flags of a VMO.
     pub fn flags(&self) -> VmoFlags {
         self.0.flags()
     }
+
+    /// Returns whether a VMO is backed by a pager, e.g., the page cache of a file.
+    pub fn has_pager(&self) -> bool {
+        self.0.has_pager()
+    }
+
+    /// Marks the page at the index as dirty, so that it will be written back to the pager.
+    pub fn mark_page_dirty(&self, page_idx: usize) -> Result<()> {
+        self.0.mark_page_dirty(page_idx)
+    }
+
+    /// Writes back the dirty pages within the specified range (in bytes) to the pager.
+    ///
+    /// The range will be rounded down and up to page boundaries.
+    pub fn write_back(&self, range: Range<usize>) -> Result<()> {
+        self.0.write_back(range)
+    }
 }
 
 /// Gets the page index range that contains the offset range of VMO.
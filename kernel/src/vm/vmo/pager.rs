@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::ops::Range;
+
 use ostd::mm::UFrame;
 
 use crate::prelude::*;
@@ -51,8 +53,21 @@ pub trait Pager: Send + Sync {
     /// call or return an error.
     fn decommit_page(&self, idx: usize) -> Result<()>;
 
+    /// Returns whether the committed frame is clean, i.e., it can be decommitted and then
+    /// provided again without losing any data.
+    ///
+    /// The VMO calls this method with its pages locked, so the pager must not sleep.
+    fn is_page_clean(&self, _frame: &UFrame) -> bool {
+        false
+    }
+
     /// Ask the pager to provide a frame at a specified index.
     /// Notify the pager that the frame will be fully overwritten soon, so pager can
     /// choose not to initialize it.
     fn commit_overwrite(&self, idx: usize) -> Result<UFrame>;
+
+    /// Ask the pager to write back the dirty frames within the specified index range.
+    ///
+    /// The pager should wait for the writeback to complete before returning.
+    fn write_back(&self, idx_range: Range<usize>) -> Result<()>;
 }
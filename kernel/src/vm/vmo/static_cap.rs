@@ -74,6 +74,19 @@ impl<R: TRights> Vmo<TRightSet<R>> {
         self.0.decommit(range)
     }
 
+    /// Decommits the clean pages specified in the range (in bytes), which will be provided
+    /// again by the pager on the next access.
+    ///
+    /// The range must be within the size of the VMO.
+    ///
+    /// # Access rights
+    ///
+    /// The method requires the Write right.
+    #[require(R > Write)]
+    pub fn decommit_clean(&self, range: Range<usize>) -> Result<()> {
+        self.0.decommit_clean(range)
+    }
+
     /// Resize the VMO by giving a new size.
     ///
     /// The VMO must be resizable.
@@ -0,0 +1,130 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The hung-task detector.
+//!
+//! A kernel thread checks the threads of all processes periodically. A thread is hung if it
+//! sleeps uninterruptibly (i.e., in the `D` state, which cannot be interrupted even by the fatal
+//! signals) and it has not been scheduled for longer than the threshold, which is known from the
+//! number of its time slices. Each hung thread is reported once in a sleep, along with the
+//! addresses found on its kernel stack.
+//!
+//! The kernel threads are not checked, since many of them sleep uninterruptibly until there is
+//! work to do.
+
+use core::time::Duration;
+
+use ostd::task::Task;
+
+use super::{print_frames, sleep, MAX_REPORT_FRAMES};
+use crate::{
+    prelude::*,
+    process::{posix_thread::AsPosixThread, process_table},
+    thread::{kernel_thread::ThreadOptions, AsThread, Tid},
+    time::clocks::MonotonicClock,
+};
+
+crate::kernel_param! {
+    /// The number of the seconds that a thread can sleep uninterruptibly before it is reported as
+    /// a hung task. The hung-task detector is disabled if it is 0.
+    static HUNG_TASK_SECS: u64 = ("watchdog.hung_task_secs", 120);
+}
+
+pub(super) fn init() {
+    let secs = HUNG_TASK_SECS.get();
+    if secs == 0 {
+        return;
+    }
+
+    let timeout = Duration::from_secs(secs);
+    // The threads are checked several times in the threshold, so that a hung thread is reported
+    // soon after the threshold is reached.
+    let period = timeout / 4;
+    ThreadOptions::new(move || {
+        let mut sleeps = BTreeMap::new();
+        loop {
+            sleep(period);
+            check_hung_tasks(&mut sleeps, timeout);
+        }
+    })
+    .spawn();
+}
+
+/// An uninterruptible sleep of a thread that is being watched.
+struct Sleep {
+    /// The number of the time slices of the thread when the sleep is found.
+    nr_slices: u64,
+    /// The time when the sleep is found.
+    since: Duration,
+    is_reported: bool,
+}
+
+fn check_hung_tasks(sleeps: &mut BTreeMap<Tid, Sleep>, timeout: Duration) {
+    let now = MonotonicClock::get().read_time();
+
+    // The tasks are not locked while the process table is locked.
+    let processes = process_table::process_table_mut()
+        .iter()
+        .cloned()
+        .collect::<Vec<_>>();
+    let tasks = processes
+        .iter()
+        .flat_map(|process| process.tasks().lock().as_slice().to_vec())
+        .collect::<Vec<_>>();
+
+    let mut new_sleeps = BTreeMap::new();
+    for task in tasks {
+        let Some(thread) = task.as_thread() else {
+            continue;
+        };
+        let Some(posix_thread) = thread.as_posix_thread() else {
+            continue;
+        };
+        if !thread.is_sleeping()
+            || thread.is_stopped()
+            || posix_thread.is_killable()
+            || posix_thread.is_frozen()
+        {
+            continue;
+        }
+
+        let tid = posix_thread.tid();
+        let nr_slices = thread.sched_attr().sched_stat().nr_slices;
+        let mut sleep = match sleeps.remove(&tid) {
+            // The thread has not been scheduled since the sleep is found.
+            Some(sleep) if sleep.nr_slices == nr_slices => sleep,
+            _ => Sleep {
+                nr_slices,
+                since: now,
+                is_reported: false,
+            },
+        };
+
+        if !sleep.is_reported && now - sleep.since >= timeout {
+            report_hung_task(&task, tid, timeout);
+            sleep.is_reported = true;
+        }
+        new_sleeps.insert(tid, sleep);
+    }
+
+    // The threads that are no longer sleeping are forgotten.
+    *sleeps = new_sleeps;
+}
+
+fn report_hung_task(task: &Task, tid: Tid, timeout: Duration) {
+    let posix_thread = task.as_posix_thread().unwrap();
+    let comm = posix_thread
+        .thread_name()
+        .lock()
+        .as_ref()
+        .and_then(|name| name.name().ok().flatten())
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    error!(
+        "INFO: task {}:{} blocked for more than {} seconds.",
+        comm,
+        tid,
+        timeout.as_secs()
+    );
+    print_frames(&task.scan_kernel_stack(MAX_REPORT_FRAMES), "? ");
+}
@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The watchdogs that report the silent hangs of the kernel on the console.
+//!
+//! There are two detectors, both of which print the stacks of the culprits:
+//! - The soft-lockup detector reports a CPU that runs the kernel code for too long without
+//!   scheduling, with the local IRQs enabled (see [`soft_lockup`]);
+//! - The hung-task detector reports a thread that sleeps uninterruptibly for too long (see
+//!   [`hung_task`]).
+//!
+//! The thresholds are set by the `watchdog.soft_lockup_secs` and `watchdog.hung_task_secs`
+//! kernel command-line arguments, and a detector is disabled if its threshold is zero. The
+//! watchdogs only report the hangs, so that the system can be diagnosed when it is stuck, e.g.,
+//! in a CI run that is killed by a timeout.
+
+use core::time::Duration;
+
+use ostd::{kallsyms::Symbolized, sync::Waiter};
+
+use crate::prelude::*;
+
+mod hung_task;
+#[cfg(target_arch = "x86_64")]
+mod soft_lockup;
+
+/// The maximum number of the frames that are printed in a report.
+const MAX_REPORT_FRAMES: usize = 32;
+
+pub(super) fn init() {
+    #[cfg(target_arch = "x86_64")]
+    soft_lockup::init();
+    hung_task::init();
+}
+
+/// Sleeps uninterruptibly for the duration.
+fn sleep(duration: Duration) {
+    let waiter = Waiter::new_pair().0;
+    let _ = waiter.wait_until_or_timeout(|| None::<()>, &duration);
+}
+
+/// Prints the addresses of the frames in a report, with the symbols if available.
+///
+/// The prefix marks whether the frames are reliable, i.e., `?` for the addresses found by
+/// scanning the stack, as in the stack dumps of Linux.
+fn print_frames(frames: &[Vaddr], prefix: &str) {
+    for &pc in frames.iter().take(MAX_REPORT_FRAMES) {
+        error!("  {}{}", prefix, Symbolized(pc));
+    }
+}
@@ -0,0 +1,103 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The soft-lockup detector.
+//!
+//! Each CPU has a watchdog thread in the highest real-time priority, which wakes up periodically
+//! and touches the timestamp of the CPU. Since the kernel code is not preempted, the thread
+//! cannot run if the CPU keeps running the kernel code without scheduling. At the timer
+//! interrupts, the interrupted stacks are sampled by OSTD, and the stack is reported if the CPU
+//! has not been touched for longer than the threshold. The lockup is reported once until the
+//! watchdog thread runs again.
+//!
+//! The lockups with the local IRQs disabled (i.e., hard lockups) cannot be detected, since the
+//! timer interrupts do not arrive. For the same reason, the `nohz_full=` CPUs are not watched,
+//! whose timer interrupts are stopped while they run a single user thread.
+
+use core::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+
+use ostd::{
+    arch::timer::{register_sample_callback, StackSample, TIMER_FREQ},
+    cpu::all_cpus,
+    cpu_local,
+};
+
+use super::{print_frames, sleep};
+use crate::{
+    prelude::*,
+    sched::{is_nohz_full, RealTimePolicy, RealTimePriority, SchedPolicy},
+    thread::kernel_thread::ThreadOptions,
+};
+
+crate::kernel_param! {
+    /// The number of the seconds that a CPU can run the kernel code without scheduling before a
+    /// soft lockup is reported. The soft-lockup detector is disabled if it is 0.
+    static SOFT_LOCKUP_SECS: u64 = ("watchdog.soft_lockup_secs", 20);
+}
+
+/// The number of the timer ticks before a soft lockup is reported.
+static THRESHOLD_TICKS: AtomicU64 = AtomicU64::new(u64::MAX);
+
+cpu_local! {
+    /// The number of the timer ticks since the watchdog thread of the CPU last ran.
+    static TICKS_SINCE_TOUCHED: AtomicU64 = AtomicU64::new(0);
+    /// Whether a soft lockup is reported since the watchdog thread of the CPU last ran.
+    static IS_REPORTED: AtomicBool = AtomicBool::new(false);
+}
+
+pub(super) fn init() {
+    let secs = SOFT_LOCKUP_SECS.get();
+    if secs == 0 {
+        return;
+    }
+
+    THRESHOLD_TICKS.store(secs.saturating_mul(TIMER_FREQ), Ordering::Relaxed);
+
+    // Like Linux, the CPUs are touched several times before the threshold is reached, so that
+    // a CPU that schedules normally is never reported.
+    let period = Duration::from_secs(secs) / 5;
+    for cpu in all_cpus().filter(|cpu| !is_nohz_full(*cpu)) {
+        ThreadOptions::new(move || loop {
+            TICKS_SINCE_TOUCHED
+                .get_on_cpu(cpu)
+                .store(0, Ordering::Relaxed);
+            IS_REPORTED.get_on_cpu(cpu).store(false, Ordering::Relaxed);
+            sleep(period);
+        })
+        .cpu_affinity(cpu.into())
+        .sched_policy(SchedPolicy::RealTime {
+            rt_prio: RealTimePriority::MIN,
+            rt_policy: RealTimePolicy::Fifo,
+        })
+        .spawn();
+    }
+
+    register_sample_callback(on_sample);
+}
+
+fn on_sample(sample: &StackSample) {
+    let cpu = sample.cpu();
+    let ticks = TICKS_SINCE_TOUCHED
+        .get_on_cpu(cpu)
+        .fetch_add(1, Ordering::Relaxed)
+        + 1;
+    if ticks < THRESHOLD_TICKS.load(Ordering::Relaxed)
+        || IS_REPORTED.get_on_cpu(cpu).swap(true, Ordering::Relaxed)
+    {
+        return;
+    }
+
+    error!(
+        "watchdog: BUG: soft lockup - CPU#{} stuck for {}s!",
+        cpu.as_usize(),
+        ticks / TIMER_FREQ
+    );
+    if sample.is_user() {
+        // The user stacks are not walked.
+        error!("  (user mode at {:#x})", sample.frames()[0]);
+    } else {
+        print_frames(sample.frames(), "");
+    }
+}
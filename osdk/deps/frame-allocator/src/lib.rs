@@ -83,4 +83,13 @@ impl GlobalFrameAllocator for FrameAllocator {
         TOTAL_FREE_SIZE.add(guard.current_cpu(), size);
         pools::add_free_memory(&guard, addr, size);
     }
+
+    fn remove_free_memory(&self, addr: Paddr, size: usize) -> bool {
+        let guard = trap::disable_local();
+        let res = pools::remove_free_memory(&guard, addr, size);
+        if res {
+            TOTAL_FREE_SIZE.sub(guard.current_cpu(), size);
+        }
+        res
+    }
 }
@@ -119,6 +119,20 @@ pub(super) fn add_free_memory(_guard: &DisabledLocalIrqGuard, addr: Paddr, size:
     global_pool.update_global_size_if_locked();
 }
 
+/// Removes the free memory from the global pool.
+///
+/// Free chunks cached in the CPU-local pools are not considered. So this may
+/// fail if some of the chunks are not yet returned to the global pool.
+pub(super) fn remove_free_memory(_guard: &DisabledLocalIrqGuard, addr: Paddr, size: usize) -> bool {
+    let mut global_pool = OnDemandGlobalLock::new();
+
+    let res = global_pool.get().take_range(addr..addr + size);
+
+    global_pool.update_global_size_if_locked();
+
+    res
+}
+
 fn do_dealloc(
     local_pool: &mut BuddySet<MAX_LOCAL_BUDDY_ORDER>,
     global_pool: &mut OnDemandGlobalLock,
@@ -1,8 +1,10 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::ops::Range;
+
 use ostd::mm::{frame::linked_list::LinkedList, Paddr};
 
-use crate::chunk::{size_of_order, BuddyOrder, FreeChunk, FreeHeadMeta};
+use crate::chunk::{size_of_order, split_to_chunks, BuddyOrder, FreeChunk, FreeHeadMeta};
 
 /// A set of free buddy chunks.
 pub(crate) struct BuddySet<const MAX_ORDER: BuddyOrder> {
@@ -98,6 +100,60 @@ impl<const MAX_ORDER: BuddyOrder> BuddySet<MAX_ORDER> {
         head_frame.reset_as_unused(); // It will "drop" the frame without up-calling us.
         Some(paddr)
     }
+
+    /// Takes all the memory in the range out of the set.
+    ///
+    /// If any part of the range is not free in the set, the set is left
+    /// untouched and this method returns `false`.
+    pub(crate) fn take_range(&mut self, range: Range<Paddr>) -> bool {
+        // Ensure that the range is fully covered by free chunks first.
+        let mut addr = range.start;
+        while addr < range.end {
+            let Some((chunk_addr, order)) = self.find_chunk(addr) else {
+                return false;
+            };
+            addr = chunk_addr + size_of_order(order);
+        }
+
+        let mut addr = range.start;
+        while addr < range.end {
+            let (chunk_addr, order) = self.find_chunk(addr).unwrap();
+            let chunk_end = chunk_addr + size_of_order(order);
+
+            let head = self.lists[order]
+                .cursor_mut_at(chunk_addr)
+                .unwrap()
+                .take_current()
+                .unwrap();
+            head.reset_as_unused();
+            self.total_size -= size_of_order(order);
+
+            // Give back the parts of the chunk that are out of the range.
+            // They won't coalesce with any chunk in the range since their
+            // buddies are all inside the chunk just taken.
+            let head_part = chunk_addr..range.start.max(chunk_addr);
+            let tail_part = range.end.min(chunk_end)..chunk_end;
+            for part in [head_part, tail_part] {
+                split_to_chunks(part.start, part.len())
+                    .for_each(|(addr, order)| self.insert_chunk(addr, order));
+            }
+
+            addr = chunk_end;
+        }
+
+        true
+    }
+
+    /// Finds the free chunk that contains the address.
+    fn find_chunk(&mut self, addr: Paddr) -> Option<(Paddr, BuddyOrder)> {
+        for (order, list) in self.lists.iter_mut().enumerate() {
+            let chunk_addr = addr & !(size_of_order(order) - 1);
+            if list.contains(chunk_addr) {
+                return Some((chunk_addr, order));
+            }
+        }
+        None
+    }
 }
 
 #[cfg(ktest)]
@@ -145,4 +201,44 @@ mod test {
         assert!(chunk == region_start);
         assert!(set.total_size() == 0);
     }
+
+    #[ktest]
+    fn test_buddy_set_take_range() {
+        use ostd::mm::PAGE_SIZE;
+
+        let region_order = 4;
+        let region_size = size_of_order(region_order);
+        let region = MockMemoryRegion::alloc(region_size);
+        let region_start = region.start_paddr();
+
+        let mut set = BuddySet::<5>::new_empty();
+        set.insert_chunk(region_start, region_order);
+
+        // Taking a range in the middle of a chunk should split the chunk.
+        let range = region_start + PAGE_SIZE..region_start + 4 * PAGE_SIZE;
+        assert!(set.take_range(range.clone()));
+        assert!(set.total_size() == region_size - range.len());
+
+        // Taking an overlapping range should fail without any effect.
+        assert!(!set.take_range(region_start..region_start + 2 * PAGE_SIZE));
+        assert!(set.total_size() == region_size - range.len());
+
+        // The remaining part can still be allocated.
+        let chunk1 = set.alloc_chunk(0).unwrap();
+        assert!(chunk1 == region_start);
+        let chunk2 = set.alloc_chunk(3).unwrap();
+        assert!(chunk2 == region_start + size_of_order(3));
+        let chunk3 = set.alloc_chunk(2).unwrap();
+        assert!(chunk3 == region_start + size_of_order(2));
+        assert!(set.total_size() == 0);
+
+        // Putting the whole region back should make it allocatable again.
+        set.insert_chunk(chunk1, 0);
+        set.insert_chunk(chunk3, 2);
+        set.insert_chunk(chunk2, 3);
+        for paddr in range.step_by(PAGE_SIZE) {
+            set.insert_chunk(paddr, 0);
+        }
+        assert!(set.alloc_chunk(region_order).unwrap() == region_start);
+    }
 }
@@ -10,6 +10,7 @@
 extern crate alloc;
 
 mod path;
+mod shard;
 mod tree;
 
 use alloc::{boxed::Box, collections::BTreeSet, string::String, vec::Vec};
@@ -23,6 +24,7 @@ use ostd::{
 };
 use owo_colors::OwoColorize;
 use path::{KtestPath, SuffixTrie};
+use shard::ShardSelector;
 use tree::{KtestCrate, KtestTree};
 
 pub enum KtestResult {
@@ -82,6 +84,9 @@ fn panic_handler(info: &core::panic::PanicInfo) -> ! {
 /// If it is `Some`, only the tests whose test path being the suffix of any paths in the whitelist
 /// will be run.
 ///
+/// Only the tests in the shard specified by the kernel command line will be run. See
+/// [`ShardSelector`] for details.
+///
 /// Returns the test result interpreted as `ok` or `FAILED`.
 ///
 /// If a test inside a crate fails, the test runner will continue to run the rest of the tests
@@ -104,6 +109,7 @@ where
     );
     let crate_set =
         crate_whitelist.map(|crates| crates.iter().copied().collect::<BTreeSet<&str>>());
+    let mut shard_selector = ShardSelector::from_kcmdline(&ostd::boot::boot_info().kernel_cmdline);
     for crate_ in tree.iter() {
         if let Some(crate_set) = &crate_set {
             if !crate_set.contains(crate_.name()) {
@@ -111,7 +117,7 @@ where
                 continue;
             }
         }
-        match run_crate_ktests(crate_, &whitelist_trie) {
+        match run_crate_ktests(crate_, &whitelist_trie, &mut shard_selector) {
             KtestResult::Ok => {}
             KtestResult::Failed => return KtestResult::Failed,
         }
@@ -120,7 +126,11 @@ where
     KtestResult::Ok
 }
 
-fn run_crate_ktests(crate_: &KtestCrate, whitelist: &Option<SuffixTrie>) -> KtestResult {
+fn run_crate_ktests(
+    crate_: &KtestCrate,
+    whitelist: &Option<SuffixTrie>,
+    shard_selector: &mut ShardSelector,
+) -> KtestResult {
     let crate_name = crate_.name();
     early_print!(
         "\nrunning {} tests in crate \"{}\"\n\n",
@@ -141,6 +151,10 @@ fn run_crate_ktests(crate_: &KtestCrate, whitelist: &Option<SuffixTrie>) -> Ktes
                     continue;
                 }
             }
+            if !shard_selector.select() {
+                filtered += 1;
+                continue;
+            }
             early_print!(
                 "test {}::{} ...",
                 test.info().module_path,
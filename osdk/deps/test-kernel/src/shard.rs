@@ -0,0 +1,73 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The selection of the tests that run in a shard.
+//!
+//! OSDK may split the tests into shards and run each shard in a separate
+//! kernel instance. The shard is specified by the kernel command line
+//! argument `ktest.shard=<index>/<count>`, which selects every `count`-th test
+//! starting from the `index`-th one. The tests are counted after filtering
+//! them by the whitelists, and in the same order as they are run.
+//!
+//! If a test times out, OSDK restarts the kernel with `ktest.skip=<n>`, which
+//! skips the first `n` tests in the shard that have been run.
+
+/// A selector that decides whether each test in the order of running belongs
+/// to the shard.
+pub struct ShardSelector {
+    index: usize,
+    count: usize,
+    skip: usize,
+    nr_tests: usize,
+    nr_shard_tests: usize,
+}
+
+impl ShardSelector {
+    /// Creates a selector from the kernel command line.
+    ///
+    /// All the tests are selected if the shard is not specified.
+    pub fn from_kcmdline(kcmdline: &str) -> Self {
+        let mut selector = Self {
+            index: 0,
+            count: 1,
+            skip: 0,
+            nr_tests: 0,
+            nr_shard_tests: 0,
+        };
+
+        for arg in kcmdline.split_whitespace() {
+            if let Some(shard) = arg.strip_prefix("ktest.shard=") {
+                let parsed = shard
+                    .split_once('/')
+                    .and_then(|(index, count)| Some((index.parse().ok()?, count.parse().ok()?)));
+                match parsed {
+                    Some((index, count)) if index < count => {
+                        selector.index = index;
+                        selector.count = count;
+                    }
+                    _ => panic!("invalid kernel command line argument `{}`", arg),
+                }
+            } else if let Some(skip) = arg.strip_prefix("ktest.skip=") {
+                selector.skip = skip
+                    .parse()
+                    .unwrap_or_else(|_| panic!("invalid kernel command line argument `{}`", arg));
+            }
+        }
+
+        selector
+    }
+
+    /// Returns whether the next test should run.
+    ///
+    /// This should be called for each test that passes the whitelists.
+    pub fn select(&mut self) -> bool {
+        let nr_tests = self.nr_tests;
+        self.nr_tests += 1;
+        if nr_tests % self.count != self.index {
+            return false;
+        }
+
+        let nr_shard_tests = self.nr_shard_tests;
+        self.nr_shard_tests += 1;
+        nr_shard_tests >= self.skip
+    }
+}
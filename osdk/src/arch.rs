@@ -62,6 +62,33 @@ impl Arch {
         }
     }
 
+    /// Get the default QEMU arguments for the architecture.
+    ///
+    /// They are used if no QEMU arguments are given in the manifest, and
+    /// select a machine that the kernel can be smoke-tested on.
+    pub fn default_qemu_args(&self) -> &'static str {
+        match self {
+            Arch::Aarch64 => "-machine virt -cpu cortex-a72 -m 2G -nographic",
+            Arch::RiscV64 => "-machine virt -cpu rv64,zba=true,zbb=true -m 2G -nographic",
+            // The default machine of QEMU works for x86-64.
+            Arch::X86_64 => "",
+            Arch::LoongArch64 => "-machine virt -m 2G -nographic",
+        }
+    }
+
+    /// Get the name of the linker script for the architecture.
+    ///
+    /// Returns `None` if the base crate does not provide a linker script
+    /// for the architecture.
+    pub fn linker_script(&self) -> Option<&'static str> {
+        match self {
+            Arch::Aarch64 => Some("aarch64.ld"),
+            Arch::RiscV64 => Some("riscv64.ld"),
+            Arch::X86_64 => Some("x86_64.ld"),
+            Arch::LoongArch64 => None,
+        }
+    }
+
     pub fn to_str(self) -> &'static str {
         match self {
             Arch::Aarch64 => "aarch64",
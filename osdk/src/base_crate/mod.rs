@@ -171,9 +171,9 @@ fn do_new_base_crate(
             ).unwrap();
         )+};
     }
-    // TODO: currently just x86_64 works; add support for other architectures
-    // here when OSTD is ready
-    include_linker_script!(["x86_64.ld", "riscv64.ld"]);
+    // All the linker scripts are written, and the one for the target
+    // architecture is selected when linking. See `Arch::linker_script`.
+    include_linker_script!(["x86_64.ld", "riscv64.ld", "aarch64.ld"]);
 
     // Overwrite the main.rs file
     let main_rs = include_str!("main.rs.template");
@@ -83,6 +83,10 @@ impl AsterBin {
         self.arch
     }
 
+    pub fn typ(&self) -> &AsterBinType {
+        &self.typ
+    }
+
     pub fn version(&self) -> &String {
         &self.version
     }
@@ -91,6 +95,14 @@ impl AsterBin {
         self.stripped
     }
 
+    /// Converts the path that is relative to the `base` directory to a full path.
+    pub fn with_base(self, base: impl AsRef<Path>) -> Self {
+        Self {
+            path: base.as_ref().join(&self.path),
+            ..self
+        }
+    }
+
     /// Copy the binary to the `base` directory and convert the path to a relative path.
     pub fn copy_to(self, base: impl AsRef<Path>) -> Self {
         let file_name = self.path.file_name().unwrap();
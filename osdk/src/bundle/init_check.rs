@@ -0,0 +1,530 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Checking the init process in the initramfs before booting.
+//!
+//! If the program given by `init=` cannot be executed, the kernel panics when it starts the init
+//! process, which says little about what is wrong. So the program is looked up in the initramfs,
+//! following the symbolic links as the kernel does, and checked to be an executable for the target
+//! architecture whose ELF interpreter or script interpreter is also present.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use crate::{arch::Arch, diagnostic::Diagnostic, error::Errno, warn_msg};
+
+/// Checks the init process given in the kernel command line against the initramfs, and exits
+/// with an error if it cannot be executed.
+///
+/// Nothing is checked if there is no `init=` argument.
+pub fn check_init(initramfs: &Path, kcmdline: &[String], arch: Arch) {
+    let Some(init) = init_path(kcmdline) else {
+        return;
+    };
+
+    let archive = match Archive::load(initramfs) {
+        Ok(Some(archive)) => archive,
+        Ok(None) => return,
+        Err(err) => {
+            warn_msg!(
+                "Skipping the check of the init process: cannot read the initramfs {}: {}",
+                initramfs.display(),
+                err
+            );
+            return;
+        }
+    };
+
+    if let Err(reason) = archive.check_executable(init, arch, 0) {
+        Diagnostic::error(format!(
+            "The init process `{}` cannot be executed from the initramfs {}: {}",
+            init,
+            initramfs.display(),
+            reason
+        ))
+        .with_code(Errno::RunBundle)
+        .with_help("check the `init=` argument in `kcmd_args` and the contents of the initramfs")
+        .emit_and_exit();
+    }
+}
+
+/// Returns the path given by `init=` in the kernel arguments, i.e., before `--`.
+fn init_path(kcmdline: &[String]) -> Option<&str> {
+    kcmdline
+        .iter()
+        .take_while(|arg| *arg != "--")
+        .find_map(|arg| arg.strip_prefix("init="))
+}
+
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+
+/// The maximum number of symbolic links followed in a lookup, which is the same as Linux.
+const MAX_SYMLINKS: usize = 40;
+/// The maximum depth of the nested script interpreters, which is the same as Linux.
+const MAX_INTERPRETER_DEPTH: usize = 4;
+/// The length of the head kept for each executable, which should contain the ELF program headers
+/// and the interpreter path.
+const HEAD_LEN: u64 = 4096;
+
+/// The entries of a CPIO archive in the `newc` format.
+#[derive(Debug, Default)]
+struct Archive {
+    /// The entries indexed by the normalized path, i.e., without the leading `/` or `./`
+    entries: HashMap<String, ArchiveEntry>,
+    /// The directories implied by the paths of the entries
+    implied_dirs: HashSet<String>,
+}
+
+#[derive(Debug)]
+struct ArchiveEntry {
+    mode: u32,
+    /// The target of a symbolic link, or the head of an executable regular file
+    content: Vec<u8>,
+}
+
+impl ArchiveEntry {
+    fn file_type(&self) -> u32 {
+        self.mode & S_IFMT
+    }
+}
+
+impl Archive {
+    /// Loads the archive, which may be compressed by gzip.
+    ///
+    /// This function returns `Ok(None)` if the archive is compressed but `gzip` is unavailable.
+    fn load(path: &Path) -> io::Result<Option<Self>> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut magic = [0; 2];
+        let is_gzip = file.read_exact(&mut magic).is_ok() && magic == [0x1f, 0x8b];
+        if !is_gzip {
+            return Self::parse(BufReader::new(File::open(path)?)).map(Some);
+        }
+
+        let mut gzip = match Command::new("gzip")
+            .arg("-dc")
+            .arg(path)
+            .stdout(Stdio::piped())
+            .spawn()
+        {
+            Ok(gzip) => gzip,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                warn_msg!("Skipping the check of the init process: `gzip` is not found");
+                return Ok(None);
+            }
+            Err(err) => return Err(err),
+        };
+        let archive = Self::parse(BufReader::new(gzip.stdout.take().unwrap()));
+        let status = gzip.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "`gzip -dc` failed with {}",
+                status
+            )));
+        }
+        archive.map(Some)
+    }
+
+    fn parse(mut reader: impl Read) -> io::Result<Self> {
+        let mut archive = Self::default();
+        // The data of hard links is only in the last entry, so the heads are assigned later.
+        let mut heads_by_ino: HashMap<u32, Vec<u8>> = HashMap::new();
+        let mut inos = HashMap::new();
+        let mut offset = 0u64;
+
+        loop {
+            let mut header = [0; 110];
+            reader.read_exact(&mut header)?;
+            offset += header.len() as u64;
+            if &header[..6] != b"070701" && &header[..6] != b"070702" {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "not a CPIO archive in the newc format",
+                ));
+            }
+            let field = |index: usize| -> io::Result<u32> {
+                let start = 6 + index * 8;
+                std::str::from_utf8(&header[start..start + 8])
+                    .ok()
+                    .and_then(|field| u32::from_str_radix(field, 16).ok())
+                    .ok_or_else(|| {
+                        io::Error::new(io::ErrorKind::InvalidData, "invalid CPIO header")
+                    })
+            };
+            let ino = field(0)?;
+            let mode = field(1)?;
+            let file_size = field(6)? as u64;
+            let name_size = field(11)? as u64;
+
+            let mut name = vec![0; name_size as usize];
+            reader.read_exact(&mut name)?;
+            offset += name_size;
+            skip(&mut reader, &mut offset, 0)?;
+            let name = String::from_utf8_lossy(name.strip_suffix(&[0]).unwrap_or(&name));
+            if name == "TRAILER!!!" {
+                break;
+            }
+
+            let file_type = mode & S_IFMT;
+            let keep_len = if file_type == S_IFLNK {
+                file_size
+            } else if file_type == S_IFREG && mode & 0o111 != 0 {
+                file_size.min(HEAD_LEN)
+            } else {
+                0
+            };
+            let mut content = vec![0; keep_len as usize];
+            reader.read_exact(&mut content)?;
+            offset += keep_len;
+            skip(&mut reader, &mut offset, file_size - keep_len)?;
+
+            let path = normalize(&name);
+            if file_type == S_IFREG && !content.is_empty() {
+                heads_by_ino.insert(ino, content.clone());
+            }
+            inos.insert(path.clone(), ino);
+            archive.add(path, ArchiveEntry { mode, content });
+        }
+
+        for (path, entry) in archive.entries.iter_mut() {
+            if entry.file_type() == S_IFREG && entry.content.is_empty() {
+                if let Some(head) = heads_by_ino.get(&inos[path]) {
+                    entry.content.clone_from(head);
+                }
+            }
+        }
+
+        Ok(archive)
+    }
+
+    fn add(&mut self, path: String, entry: ArchiveEntry) {
+        let mut parent = path.as_str();
+        while let Some((dir, _)) = parent.rsplit_once('/') {
+            self.implied_dirs.insert(dir.to_owned());
+            parent = dir;
+        }
+        self.entries.insert(path, entry);
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        path.is_empty()
+            || self.implied_dirs.contains(path)
+            || self
+                .entries
+                .get(path)
+                .is_some_and(|entry| entry.file_type() == S_IFDIR)
+    }
+
+    /// Looks up the absolute path, following the symbolic links.
+    fn lookup(&self, path: &str) -> Result<&ArchiveEntry, String> {
+        let mut resolved: Vec<String> = Vec::new();
+        let mut pending: Vec<String> = components(path).rev().collect();
+        let mut num_symlinks = 0;
+
+        while let Some(component) = pending.pop() {
+            match component.as_str() {
+                "." => continue,
+                ".." => {
+                    resolved.pop();
+                    continue;
+                }
+                _ => (),
+            }
+
+            let parent = resolved.join("/");
+            if !self.is_dir(&parent) {
+                return Err(format!("`/{}` is not a directory", parent));
+            }
+            resolved.push(component);
+            let current = resolved.join("/");
+
+            let Some(entry) = self.entries.get(&current) else {
+                if pending.is_empty() && self.implied_dirs.contains(&current) {
+                    return Err(format!("`/{}` is a directory", current));
+                }
+                return Err(format!("`/{}` does not exist", current));
+            };
+            if entry.file_type() != S_IFLNK {
+                continue;
+            }
+
+            num_symlinks += 1;
+            if num_symlinks > MAX_SYMLINKS {
+                return Err(format!("too many levels of symbolic links in `{}`", path));
+            }
+            let target = String::from_utf8_lossy(&entry.content).into_owned();
+            resolved.pop();
+            if target.starts_with('/') {
+                resolved.clear();
+            }
+            pending.extend(components(&target).rev());
+        }
+
+        let current = resolved.join("/");
+        self.entries
+            .get(&current)
+            .ok_or_else(|| format!("`/{}` is a directory", current))
+    }
+
+    /// Checks that the program at the path can be executed by the kernel for the architecture.
+    fn check_executable(&self, path: &str, arch: Arch, depth: usize) -> Result<(), String> {
+        if !path.starts_with('/') {
+            return Err(format!("`{}` is not an absolute path", path));
+        }
+
+        let entry = self.lookup(path)?;
+        if entry.file_type() != S_IFREG {
+            return Err(format!("`{}` is not a regular file", path));
+        }
+        if entry.mode & 0o111 == 0 {
+            return Err(format!(
+                "`{}` is not executable (its mode is {:o})",
+                path,
+                entry.mode & 0o7777
+            ));
+        }
+
+        let head = &entry.content;
+        let interpreter = if head.starts_with(b"\x7fELF") {
+            check_elf(path, head, arch)?
+        } else if let Some(line) = head.strip_prefix(b"#!") {
+            let line = line.split(|byte| *byte == b'\n').next().unwrap();
+            let interpreter = String::from_utf8_lossy(line)
+                .split_whitespace()
+                .next()
+                .map(str::to_owned)
+                .ok_or_else(|| format!("the script `{}` has no interpreter", path))?;
+            Some(interpreter)
+        } else {
+            return Err(format!("`{}` is neither an ELF file nor a script", path));
+        };
+
+        let Some(interpreter) = interpreter else {
+            return Ok(());
+        };
+        if depth >= MAX_INTERPRETER_DEPTH {
+            return Err(format!("too many levels of interpreters for `{}`", path));
+        }
+        self.check_executable(&interpreter, arch, depth + 1)
+            .map_err(|reason| format!("the interpreter of `{}` is invalid: {}", path, reason))
+    }
+}
+
+/// Checks the ELF header and returns the path of the ELF interpreter, if any.
+///
+/// The interpreter is not checked if it is beyond the kept head of the file.
+fn check_elf(path: &str, head: &[u8], arch: Arch) -> Result<Option<String>, String> {
+    const ELFCLASS64: u8 = 2;
+    const ELFDATA2LSB: u8 = 1;
+    const PT_INTERP: u32 = 3;
+
+    let truncated = || format!("`{}` is a truncated ELF file", path);
+    let u16_at = |offset: usize| -> Result<u16, String> {
+        let bytes = head.get(offset..offset + 2).ok_or_else(truncated)?;
+        Ok(u16::from_le_bytes(bytes.try_into().unwrap()))
+    };
+    let u32_at = |offset: usize| -> Result<u32, String> {
+        let bytes = head.get(offset..offset + 4).ok_or_else(truncated)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    };
+    let u64_at = |offset: usize| -> Result<u64, String> {
+        let bytes = head.get(offset..offset + 8).ok_or_else(truncated)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    };
+
+    // All the supported architectures are 64-bit and little-endian.
+    let machine = u16_at(18)?;
+    if head[4] != ELFCLASS64 || head[5] != ELFDATA2LSB || machine != elf_machine(arch) {
+        return Err(format!(
+            "`{}` is an ELF file for {}, but the kernel is built for {}",
+            path,
+            elf_machine_name(head[4], machine),
+            arch
+        ));
+    }
+
+    let phoff = u64_at(32)? as usize;
+    let phentsize = u16_at(54)? as usize;
+    let phnum = u16_at(56)? as usize;
+    for index in 0..phnum {
+        let phdr = phoff + index * phentsize;
+        if phdr + phentsize > head.len() {
+            break;
+        }
+        if u32_at(phdr)? != PT_INTERP {
+            continue;
+        }
+        let offset = u64_at(phdr + 8)? as usize;
+        let size = u64_at(phdr + 32)? as usize;
+        let Some(interpreter) = head.get(offset..offset + size) else {
+            break;
+        };
+        let interpreter = interpreter.strip_suffix(&[0]).unwrap_or(interpreter);
+        return Ok(Some(String::from_utf8_lossy(interpreter).into_owned()));
+    }
+
+    Ok(None)
+}
+
+fn elf_machine(arch: Arch) -> u16 {
+    match arch {
+        Arch::Aarch64 => 183,
+        Arch::RiscV64 => 243,
+        Arch::X86_64 => 62,
+        Arch::LoongArch64 => 258,
+    }
+}
+
+fn elf_machine_name(class: u8, machine: u16) -> String {
+    let bits = if class == 2 { "64-bit" } else { "32-bit" };
+    let name = match machine {
+        3 => "x86".to_owned(),
+        40 => "arm".to_owned(),
+        62 => "x86_64".to_owned(),
+        183 => "aarch64".to_owned(),
+        243 => "riscv".to_owned(),
+        258 => "loongarch".to_owned(),
+        _ => format!("machine {}", machine),
+    };
+    format!("{} {}", bits, name)
+}
+
+/// Normalizes a path in the archive by removing the leading `/`, `./` and the trailing `/`.
+fn normalize(name: &str) -> String {
+    components(name)
+        .filter(|component| component != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn components(path: &str) -> impl DoubleEndedIterator<Item = String> + '_ {
+    path.split('/')
+        .filter(|component| !component.is_empty())
+        .map(str::to_owned)
+}
+
+/// Skips `len` bytes and then the padding to the 4-byte boundary.
+fn skip(reader: &mut impl Read, offset: &mut u64, len: u64) -> io::Result<()> {
+    let len = len + (*offset + len).next_multiple_of(4) - (*offset + len);
+    let skipped = io::copy(&mut reader.take(len), &mut io::sink())?;
+    if skipped != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    *offset += len;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    use super::*;
+    use crate::bundle::initramfs::build_from_dir;
+
+    /// Makes the head of a 64-bit ELF file for the machine, with the interpreter if given.
+    fn elf(machine: u16, interpreter: Option<&str>) -> Vec<u8> {
+        let mut elf = vec![0; 64];
+        elf[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        elf[18..20].copy_from_slice(&machine.to_le_bytes());
+        elf[32..40].copy_from_slice(&64u64.to_le_bytes());
+        elf[54..56].copy_from_slice(&56u16.to_le_bytes());
+        if let Some(interpreter) = interpreter {
+            elf[56..58].copy_from_slice(&1u16.to_le_bytes());
+            let mut phdr = vec![0; 56];
+            phdr[..4].copy_from_slice(&3u32.to_le_bytes());
+            phdr[8..16].copy_from_slice(&120u64.to_le_bytes());
+            phdr[32..40].copy_from_slice(&(interpreter.len() as u64 + 1).to_le_bytes());
+            elf.extend(phdr);
+            elf.extend(interpreter.as_bytes());
+            elf.push(0);
+        }
+        elf
+    }
+
+    fn add_file(root: &Path, path: &str, content: &[u8], mode: u32) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    #[test]
+    fn check_executables() {
+        let base =
+            std::env::temp_dir().join(format!("osdk-init-check-test-{}", std::process::id()));
+        let root = base.join("rootfs");
+        let _ = fs::remove_dir_all(&base);
+
+        add_file(&root, "usr/bin/busybox", &elf(62, None), 0o755);
+        add_file(
+            &root,
+            "usr/bin/dynamic",
+            &elf(62, Some("/lib/ld.so")),
+            0o755,
+        );
+        add_file(
+            &root,
+            "usr/bin/broken",
+            &elf(62, Some("/lib/missing.so")),
+            0o755,
+        );
+        add_file(&root, "usr/bin/riscv", &elf(243, None), 0o755);
+        add_file(&root, "usr/bin/script", b"#!/bin/sh -e\necho\n", 0o755);
+        add_file(&root, "usr/bin/data", b"data", 0o644);
+        add_file(&root, "lib/ld.so", &elf(62, None), 0o755);
+        std::os::unix::fs::symlink("../usr/bin/busybox", root.join("lib/busybox")).unwrap();
+        fs::create_dir_all(root.join("bin")).unwrap();
+        std::os::unix::fs::symlink("/lib/busybox", root.join("bin/sh")).unwrap();
+        std::os::unix::fs::symlink("loop", root.join("bin/loop")).unwrap();
+
+        let archive_path = build_from_dir(&root, &base.join("output"));
+        let archive = Archive::parse(File::open(archive_path).unwrap()).unwrap();
+        let check = |path| archive.check_executable(path, Arch::X86_64, 0);
+
+        assert_eq!(check("/usr/bin/busybox"), Ok(()));
+        assert_eq!(check("/bin/sh"), Ok(()));
+        assert_eq!(check("/usr/bin/dynamic"), Ok(()));
+        assert_eq!(check("/usr/bin/script"), Ok(()));
+        assert_eq!(check("/usr/../bin/./sh"), Ok(()));
+
+        assert!(check("/usr/bin/missing")
+            .unwrap_err()
+            .contains("`/usr/bin/missing` does not exist"));
+        assert!(check("/usr/bin/broken")
+            .unwrap_err()
+            .contains("`/lib/missing.so` does not exist"));
+        assert!(check("/usr/bin/riscv")
+            .unwrap_err()
+            .contains("64-bit riscv"));
+        assert!(check("/usr/bin/data")
+            .unwrap_err()
+            .contains("is not executable"));
+        assert!(check("/usr/bin/busybox/sh")
+            .unwrap_err()
+            .contains("is not a directory"));
+        assert!(check("/bin/loop")
+            .unwrap_err()
+            .contains("too many levels of symbolic links"));
+        assert!(check("/usr/bin")
+            .unwrap_err()
+            .contains("is not a regular file"));
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn find_init_path() {
+        let kcmdline = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            init_path(&kcmdline(&["console=hvc0", "init=/bin/sh", "--", "-l"])),
+            Some("/bin/sh")
+        );
+        assert_eq!(init_path(&kcmdline(&["--", "init=/bin/sh"])), None);
+    }
+}
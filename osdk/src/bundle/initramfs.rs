@@ -0,0 +1,482 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Building the initramfs from a directory.
+//!
+//! The directory is packed into a CPIO archive in the `newc` format, which is what the kernel
+//! expects. The content hashes of the packed files are recorded alongside the archive, so that the
+//! archive is regenerated only if the contents of the directory change. A file is hashed again
+//! only if its size or modified time differs from the recorded one.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufWriter, Read, Write},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{error::Errno, exit_with_error};
+
+/// Builds the initramfs from the `source` directory and returns the path of the CPIO archive.
+///
+/// The archive is placed in `output_dir`. If the contents of the directory are unchanged since
+/// the archive was generated, the existing archive is reused as is, so its modified time is kept
+/// and the bundles containing it remain valid.
+pub fn build_from_dir(source: &Path, output_dir: &Path) -> PathBuf {
+    let name = source
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "initramfs".to_owned());
+    let archive_path = output_dir.join(format!("{}.cpio", name));
+    let cache_path = output_dir.join(format!("{}.cpio.hashes.toml", name));
+
+    let cache = archive_path
+        .exists()
+        .then(|| HashCache::load(&cache_path))
+        .flatten()
+        .unwrap_or_default();
+
+    let entries = collect_entries(source, &cache).unwrap_or_else(|err| {
+        exit_with_error!(
+            Errno::BuildCrate,
+            "Failed to read the initramfs directory {}: {}",
+            source.display(),
+            err
+        )
+    });
+    let digest = digest_of(&entries);
+    if archive_path.exists() && cache.digest == digest {
+        info!(
+            "Reusing the initramfs archive: {} is unchanged",
+            source.display()
+        );
+        return archive_path;
+    }
+
+    info!("Generating the initramfs archive from {}", source.display());
+    fs::create_dir_all(output_dir).unwrap();
+    // The archive may be hard-linked into existing bundles, so a new file replaces it instead of
+    // overwriting it in place.
+    let tmp_path = output_dir.join(format!("{}.cpio.tmp", name));
+    let result = write_archive(source, &entries, &tmp_path)
+        .and_then(|_| fs::rename(&tmp_path, &archive_path));
+    if let Err(err) = result {
+        let _ = fs::remove_file(&tmp_path);
+        exit_with_error!(
+            Errno::BuildCrate,
+            "Failed to generate the initramfs archive {}: {}",
+            archive_path.display(),
+            err
+        );
+    }
+    HashCache::new(digest, &entries).save(&cache_path);
+
+    archive_path
+}
+
+/// An entry to pack into the archive.
+#[derive(Debug)]
+struct Entry {
+    /// The path relative to the source directory
+    path: String,
+    kind: EntryKind,
+    /// The file type and permission bits of the `st_mode` field
+    mode: u32,
+    uid: u32,
+    gid: u32,
+    size: u64,
+    modified_time: SystemTime,
+}
+
+#[derive(Debug)]
+enum EntryKind {
+    Dir,
+    /// A regular file with the hex-encoded hash of its contents
+    File {
+        hash: String,
+    },
+    Symlink {
+        target: String,
+    },
+}
+
+/// Collects the entries in the `source` directory recursively.
+///
+/// A directory always appears before its children, which is required by the kernel to unpack the
+/// archive. The children are sorted by name, so the order is stable across runs.
+fn collect_entries(source: &Path, cache: &HashCache) -> io::Result<Vec<Entry>> {
+    let cached_files: HashMap<&str, &CachedFile> = cache
+        .files
+        .iter()
+        .map(|file| (file.path.as_str(), file))
+        .collect();
+
+    let mut entries = Vec::new();
+    let mut pending_dirs = vec![source.to_path_buf()];
+    while let Some(dir) = pending_dirs.pop() {
+        let mut children = fs::read_dir(&dir)?
+            .map(|child| child.map(|child| child.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        children.sort();
+
+        let mut child_dirs = Vec::new();
+        for child in children {
+            let path = child
+                .strip_prefix(source)
+                .unwrap()
+                .to_string_lossy()
+                .into_owned();
+            let metadata = fs::symlink_metadata(&child)?;
+            let modified_time = metadata.modified()?;
+
+            let kind = if metadata.is_dir() {
+                child_dirs.push(child.clone());
+                EntryKind::Dir
+            } else if metadata.is_symlink() {
+                let target = fs::read_link(&child)?.to_string_lossy().into_owned();
+                EntryKind::Symlink { target }
+            } else if metadata.is_file() {
+                let hash = match cached_files.get(path.as_str()) {
+                    Some(cached)
+                        if cached.size == metadata.len()
+                            && cached.modified_time == modified_time =>
+                    {
+                        cached.hash.clone()
+                    }
+                    _ => hash_file(&child)?,
+                };
+                EntryKind::File { hash }
+            } else {
+                warn!(
+                    "Skipping {} in the initramfs: unsupported file type",
+                    child.display()
+                );
+                continue;
+            };
+
+            entries.push(Entry {
+                path,
+                kind,
+                mode: metadata.mode(),
+                uid: metadata.uid(),
+                gid: metadata.gid(),
+                size: metadata.len(),
+                modified_time,
+            });
+        }
+
+        // Visit the child directories in order, right after the entries of this directory.
+        pending_dirs.extend(child_dirs.into_iter().rev());
+    }
+
+    Ok(entries)
+}
+
+fn hash_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = StableHasher::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let len = file.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        hasher.write(&buf[..len]);
+    }
+    Ok(hasher.finish())
+}
+
+/// Computes the digest of everything that affects the contents of the archive.
+fn digest_of(entries: &[Entry]) -> String {
+    let mut hasher = StableHasher::new();
+    for entry in entries {
+        hasher.write_field(entry.path.as_bytes());
+        match &entry.kind {
+            EntryKind::Dir => hasher.write(b"d"),
+            EntryKind::File { hash } => {
+                hasher.write(b"f");
+                hasher.write_field(hash.as_bytes());
+            }
+            EntryKind::Symlink { target } => {
+                hasher.write(b"l");
+                hasher.write_field(target.as_bytes());
+            }
+        }
+        hasher.write(&entry.mode.to_le_bytes());
+        hasher.write(&entry.uid.to_le_bytes());
+        hasher.write(&entry.gid.to_le_bytes());
+    }
+    hasher.finish()
+}
+
+/// A 64-bit FNV-1a hasher.
+///
+/// The hashes are saved and compared in later builds, so they must not change across Rust
+/// releases, which is not guaranteed by [`std::hash::DefaultHasher`].
+struct StableHasher(u64);
+
+impl StableHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    /// Writes a field of variable length, which is prefixed with its length so that it cannot
+    /// be confused with the following fields.
+    fn write_field(&mut self, bytes: &[u8]) {
+        self.write(&(bytes.len() as u64).to_le_bytes());
+        self.write(bytes);
+    }
+
+    /// Returns the hash in hexadecimal.
+    fn finish(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
+/// Writes the entries into a CPIO archive in the `newc` format.
+fn write_archive(source: &Path, entries: &[Entry], archive_path: &Path) -> io::Result<()> {
+    let mut archive = CpioWriter::new(BufWriter::new(File::create(archive_path)?));
+    for (index, entry) in entries.iter().enumerate() {
+        // The inode numbers only need to be unique, since hard links are not preserved.
+        let ino = index as u32 + 1;
+        match &entry.kind {
+            EntryKind::Dir => archive.write_entry(ino, entry, &mut io::empty(), 0)?,
+            EntryKind::File { .. } => {
+                let mut file = File::open(source.join(&entry.path))?;
+                // The file may have changed since it was hashed, so the length of its contents
+                // is taken now and checked after writing.
+                let len = file.metadata()?.len();
+                archive.write_entry(ino, entry, &mut file, len)?;
+            }
+            EntryKind::Symlink { target } => {
+                let len = target.len() as u64;
+                archive.write_entry(ino, entry, &mut target.as_bytes(), len)?;
+            }
+        }
+    }
+    archive.finish()
+}
+
+struct CpioWriter<W: Write> {
+    inner: W,
+    offset: u64,
+}
+
+impl<W: Write> CpioWriter<W> {
+    const MAGIC: &'static str = "070701";
+    const TRAILER: &'static str = "TRAILER!!!";
+
+    fn new(inner: W) -> Self {
+        Self { inner, offset: 0 }
+    }
+
+    fn write_entry(
+        &mut self,
+        ino: u32,
+        entry: &Entry,
+        data: &mut dyn Read,
+        len: u64,
+    ) -> io::Result<()> {
+        let mtime = entry
+            .modified_time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let nlink = if matches!(entry.kind, EntryKind::Dir) {
+            2
+        } else {
+            1
+        };
+        self.write_header(
+            &[
+                ino,
+                entry.mode,
+                entry.uid,
+                entry.gid,
+                nlink,
+                mtime as u32,
+                checked_u32(len)?,
+            ],
+            &entry.path,
+        )?;
+
+        let copied = io::copy(&mut data.take(len), &mut self.inner)?;
+        if copied != len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("{} is truncated while being packed", entry.path),
+            ));
+        }
+        self.offset += copied;
+        self.pad()
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        self.write_header(&[0, 0, 0, 0, 1, 0, 0], Self::TRAILER)?;
+        self.inner.flush()
+    }
+
+    /// Writes the header, in which the fields after the file size are zeros except the length
+    /// of the name.
+    fn write_header(&mut self, fields: &[u32; 7], name: &str) -> io::Result<()> {
+        let mut header = String::from(Self::MAGIC);
+        for field in fields {
+            header.push_str(&format!("{:08X}", field));
+        }
+        // The device numbers of the file and of the device file itself
+        header.push_str(&format!("{:08X}", 0).repeat(4));
+        // The length of the name, including the trailing NUL
+        header.push_str(&format!("{:08X}", name.len() + 1));
+        // The checksum, which is unused in the `newc` format
+        header.push_str(&format!("{:08X}", 0));
+
+        self.inner.write_all(header.as_bytes())?;
+        self.inner.write_all(name.as_bytes())?;
+        self.inner.write_all(&[0])?;
+        self.offset += (header.len() + name.len() + 1) as u64;
+        self.pad()
+    }
+
+    /// Pads the archive to the 4-byte boundary.
+    fn pad(&mut self) -> io::Result<()> {
+        let padding = self.offset.next_multiple_of(4) - self.offset;
+        self.inner.write_all(&[0; 3][..padding as usize])?;
+        self.offset += padding;
+        Ok(())
+    }
+}
+
+fn checked_u32(len: u64) -> io::Result<u32> {
+    u32::try_from(len).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "files larger than 4 GiB cannot be packed in the newc format",
+        )
+    })
+}
+
+/// The recorded content hashes of the files in the last generated archive.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HashCache {
+    digest: String,
+    files: Vec<CachedFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedFile {
+    path: String,
+    size: u64,
+    modified_time: SystemTime,
+    hash: String,
+}
+
+impl HashCache {
+    fn new(digest: String, entries: &[Entry]) -> Self {
+        let files = entries
+            .iter()
+            .filter_map(|entry| {
+                let EntryKind::File { hash } = &entry.kind else {
+                    return None;
+                };
+                Some(CachedFile {
+                    path: entry.path.clone(),
+                    size: entry.size,
+                    modified_time: entry.modified_time,
+                    hash: hash.clone(),
+                })
+            })
+            .collect();
+        Self { digest, files }
+    }
+
+    fn load(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        toml::from_str(&content).ok()
+    }
+
+    fn save(&self, path: &Path) {
+        // The hashes are only used to skip regenerating the archive, so failing to save them is
+        // not fatal.
+        let result = toml::to_string(self)
+            .map_err(io::Error::other)
+            .and_then(|content| fs::write(path, content));
+        if let Err(err) = result {
+            warn!(
+                "Failed to save the initramfs hashes to {}: {}",
+                path.display(),
+                err
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn incremental_build() {
+        let base = std::env::temp_dir().join(format!("osdk-initramfs-test-{}", std::process::id()));
+        let source = base.join("rootfs");
+        let output = base.join("output");
+        let _ = fs::remove_dir_all(&base);
+        fs::create_dir_all(source.join("etc")).unwrap();
+        fs::write(source.join("etc/hostname"), "asterinas\n").unwrap();
+        std::os::unix::fs::symlink("etc/hostname", source.join("hostname")).unwrap();
+
+        let archive = build_from_dir(&source, &output);
+        let content = fs::read(&archive).unwrap();
+        assert_eq!(content.len() % 4, 0);
+        assert!(content.starts_with(b"070701"));
+        let names: Vec<_> = ["etc\0", "hostname\0", "etc/hostname\0", "TRAILER!!!\0"]
+            .iter()
+            .map(|name| find(&content, name.as_bytes()).unwrap())
+            .collect();
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(HashCache::load(&output.join("rootfs.cpio.hashes.toml")).is_some());
+
+        // Touching a file without changing its contents does not regenerate the archive.
+        let modified_time = archive.metadata().unwrap().modified().unwrap();
+        File::options()
+            .write(true)
+            .open(source.join("etc/hostname"))
+            .unwrap()
+            .set_modified(SystemTime::now())
+            .unwrap();
+        assert_eq!(build_from_dir(&source, &output), archive);
+        assert_eq!(
+            archive.metadata().unwrap().modified().unwrap(),
+            modified_time
+        );
+
+        // Changing the contents regenerates the archive.
+        fs::write(source.join("etc/hostname"), "asterinas-dev\n").unwrap();
+        build_from_dir(&source, &output);
+        assert!(find(&fs::read(&archive).unwrap(), b"asterinas-dev\n").is_some());
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn stable_hash() {
+        let mut hasher = StableHasher::new();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), "af63dc4c8601ec8c");
+    }
+
+    fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        haystack
+            .windows(needle.len())
+            .position(|window| window == needle)
+    }
+}
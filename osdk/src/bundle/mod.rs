@@ -2,27 +2,36 @@
 
 pub mod bin;
 pub mod file;
+pub mod init_check;
+pub mod initramfs;
+pub mod panic_report;
+pub mod syscall_audit;
 pub mod vm_image;
 
-use bin::AsterBin;
+use bin::{AsterBin, AsterBinType};
 use file::{BundleFile, Initramfs};
-use std::process;
+use panic_report::PanicReport;
+use syscall_audit::MissingSyscalls;
 use vm_image::{AsterVmImage, AsterVmImageType};
 
 use std::{
+    ffi::OsString,
     path::{Path, PathBuf},
-    process::Command,
+    process::{Command, ExitStatus},
     time::SystemTime,
 };
 
 use crate::{
+    arch::Arch,
     config::{
-        scheme::{ActionChoice, BootMethod},
+        scheme::{ActionChoice, BootMethod, DirectBootProtocol},
         Config,
     },
+    diagnostic::{exit_on_launch_failure, Diagnostic},
     error::Errno,
-    error_msg,
+    error_msg, exit_with_error,
     util::DirGuard,
+    warn_msg,
 };
 
 /// The osdk bundle artifact that stores as `bundle` directory.
@@ -50,14 +59,21 @@ impl Bundle {
     /// This function creates a new `Bundle` without adding any files.
     pub fn new(path: impl AsRef<Path>, config: &Config, action: ActionChoice) -> Self {
         std::fs::create_dir_all(path.as_ref()).unwrap();
-        let config_initramfs = match action {
-            ActionChoice::Run => config.run.boot.initramfs.as_ref(),
-            ActionChoice::Test => config.test.boot.initramfs.as_ref(),
+        let boot = match action {
+            ActionChoice::Run => &config.run.boot,
+            ActionChoice::Test => &config.test.boot,
         };
-        let initramfs = if let Some(ref initramfs) = config_initramfs {
+        let initramfs = if let Some(ref initramfs) = boot.initramfs {
             if !initramfs.exists() {
-                error_msg!("initramfs file not found: {}", initramfs.display());
-                process::exit(Errno::BuildCrate as _);
+                exit_with_error!(
+                    Errno::BuildCrate,
+                    "initramfs file not found: {}",
+                    initramfs.display()
+                );
+            }
+            // The kernel does not start the init process when running the tests.
+            if action == ActionChoice::Run {
+                init_check::check_init(initramfs, &boot.kcmdline, config.target_arch);
             }
             Some(Initramfs::new(initramfs).copy_to(&path))
         } else {
@@ -139,9 +155,25 @@ impl Bundle {
         // Checkout if the files on disk supports the boot method
         match config_action.boot.method {
             BootMethod::QemuDirect => {
-                if self.manifest.aster_bin.is_none() {
+                let Some(ref aster_bin) = self.manifest.aster_bin else {
                     return Err("Kernel binary is required for direct QEMU booting".to_owned());
                 };
+                let is_expected_bin = match (config_action.boot.protocol, aster_bin.typ()) {
+                    (DirectBootProtocol::Elf, AsterBinType::Elf(_)) => true,
+                    (DirectBootProtocol::LinuxEfiHandover, AsterBinType::BzImage(meta)) => {
+                        meta.support_efi_handover
+                    }
+                    (DirectBootProtocol::LinuxLegacy, AsterBinType::BzImage(meta)) => {
+                        meta.support_legacy32_boot
+                    }
+                    _ => false,
+                };
+                if !is_expected_bin {
+                    return Err(
+                        "Kernel binary in the bundle does not support the direct boot protocol"
+                            .to_owned(),
+                    );
+                }
             }
             BootMethod::GrubRescueIso => {
                 let Some(ref vm_image) = self.manifest.vm_image else {
@@ -183,16 +215,200 @@ impl Bundle {
         Ok(())
     }
 
+    /// Returns the kernel binary in the bundle with its full path.
+    pub fn aster_bin(&self) -> Option<AsterBin> {
+        let aster_bin = self.manifest.aster_bin.clone()?;
+        Some(aster_bin.with_base(&self.path))
+    }
+
+    /// Returns the full path of the VM image in the bundle.
+    pub fn vm_image_path(&self) -> Option<PathBuf> {
+        let vm_image = self.manifest.vm_image.as_ref()?;
+        Some(self.path.join(vm_image.path()))
+    }
+
+    /// Returns the full path of the initramfs in the bundle.
+    pub fn initramfs_path(&self) -> Option<PathBuf> {
+        let initramfs = self.manifest.initramfs.as_ref()?;
+        Some(self.path.join(initramfs.path()))
+    }
+
     pub fn last_modified_time(&self) -> SystemTime {
         self.manifest.last_modified
     }
 
     pub fn run(&self, config: &Config, action: ActionChoice) {
+        if let Err(exit_code) = self.try_run(config, action) {
+            std::process::exit(exit_code);
+        }
+    }
+
+    /// Runs the bundle once without exiting on the failure of the kernel.
+    ///
+    /// On failure, it returns the exit code that OSDK should exit with,
+    /// i.e., [`Errno::KernelPanic`] if the kernel emits a panic report, 1 if
+    /// the kernel reports a failure and 2 if the failure is unknown.
+    pub fn try_run(&self, config: &Config, action: ActionChoice) -> Result<(), i32> {
+        let mut qemu_cmd = self.qemu_command(config, action);
+
+        // Capture the panic report from the dedicated debug console.
+        let panic_report_path = config.work_dir.join("panic-report.log");
+        let _ = std::fs::remove_file(&panic_report_path);
+        let qemu_args = match action {
+            ActionChoice::Run => &config.run.qemu.args,
+            ActionChoice::Test => &config.test.qemu.args,
+        };
+        if config.target_arch == Arch::X86_64 && !qemu_args.contains("isa-debugcon") {
+            qemu_cmd.arg("-chardev").arg(format!(
+                "file,id=osdk-panic-report,path={}",
+                panic_report_path.display()
+            ));
+            qemu_cmd
+                .arg("-device")
+                .arg("isa-debugcon,iobase=0xe9,chardev=osdk-panic-report");
+        }
+
+        self.run_pre_hook(config, action)?;
+
+        info!("Running QEMU: {:#?}", qemu_cmd);
+
+        let exit_status = qemu_cmd.status().unwrap_or_else(|err| {
+            exit_on_launch_failure(
+                &qemu_cmd.get_program().to_string_lossy(),
+                err,
+                &qemu_install_help(config),
+            )
+        });
+
+        // Find the QEMU output in "qemu.log", read it and check if it failed with a panic.
+        // Setting a QEMU log is required for source line stack trace because piping the output
+        // is less desirable when running QEMU with serial redirected to standard I/O.
+        let qemu_log_path = config.work_dir.join("qemu.log");
+        let panic_report = [&panic_report_path, &qemu_log_path]
+            .into_iter()
+            .filter_map(|path| std::fs::read(path).ok())
+            .find_map(|output| PanicReport::parse(&String::from_utf8_lossy(&output)));
+        if let Some(panic_report) = &panic_report {
+            self.print_panic_report(panic_report);
+        } else if let Ok(file) = std::fs::File::open(&qemu_log_path) {
+            if let Some(aster_bin) = &self.manifest.aster_bin {
+                crate::util::trace_panic_from_log(file, self.path.join(aster_bin.path()));
+            }
+        }
+        if let Ok(output) = std::fs::read(&qemu_log_path) {
+            print_missing_syscalls(&MissingSyscalls::parse(&String::from_utf8_lossy(&output)));
+        }
+
+        // FIXME: When panicking it sometimes returns success, why?
+        let result = if panic_report.is_some() {
+            Err(Errno::KernelPanic as _)
+        } else {
+            kernel_exit_result(exit_status)
+        };
+
+        self.run_post_hook(config, action, result)
+    }
+
+    /// Runs the pre-run hook, if any.
+    ///
+    /// On failure, it returns the exit code that OSDK should exit with.
+    pub fn run_pre_hook(&self, config: &Config, action: ActionChoice) -> Result<(), i32> {
+        let hooks = match action {
+            ActionChoice::Run => &config.run.hooks,
+            ActionChoice::Test => &config.test.hooks,
+        };
+        if let Some(pre) = &hooks.pre {
+            if !run_hook(pre, config, &self.hook_envs(config, action)) {
+                error_msg!("The pre-run hook `{}` failed", pre);
+                return Err(Errno::RunBundle as _);
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the post-run hook, if any, with the result of running the bundle.
+    ///
+    /// It returns the result of running the bundle, or an error if the hook fails.
+    pub fn run_post_hook(
+        &self,
+        config: &Config,
+        action: ActionChoice,
+        result: Result<(), i32>,
+    ) -> Result<(), i32> {
+        let hooks = match action {
+            ActionChoice::Run => &config.run.hooks,
+            ActionChoice::Test => &config.test.hooks,
+        };
+        if let Some(post) = &hooks.post {
+            let mut hook_envs = self.hook_envs(config, action);
+            let exit_code = result.err().unwrap_or(0);
+            hook_envs.push(("OSDK_EXIT_STATUS", exit_code.to_string()));
+            if !run_hook(post, config, &hook_envs) {
+                error_msg!("The post-run hook `{}` failed", post);
+                return result.and(Err(Errno::RunBundle as _));
+            }
+        }
+        result
+    }
+
+    /// Prints the panic report, with the addresses symbolized against the kernel ELF.
+    fn print_panic_report(&self, panic_report: &PanicReport) {
+        Diagnostic::error(format!("The kernel panicked: {}", panic_report.summary()))
+            .with_code(Errno::KernelPanic)
+            .emit();
+
+        println!("{}", panic_report.render(&self.elf_path()));
+    }
+
+    /// Returns the path of the kernel ELF in the bundle, or an empty path if
+    /// there is no kernel ELF.
+    pub fn elf_path(&self) -> PathBuf {
+        self.manifest
+            .aster_bin
+            .as_ref()
+            .map(|aster_bin| self.path.join(aster_bin.path()))
+            .unwrap_or_default()
+    }
+
+    /// Returns the environment variables that are exposed to the run hooks.
+    fn hook_envs(&self, config: &Config, action: ActionChoice) -> Vec<(&'static str, String)> {
+        let action = match action {
+            ActionChoice::Run => &config.run,
+            ActionChoice::Test => &config.test,
+        };
+
+        let image_path = match action.boot.method {
+            BootMethod::QemuDirect => self.manifest.aster_bin.as_ref().map(|bin| bin.path()),
+            BootMethod::GrubRescueIso | BootMethod::GrubQcow2 => {
+                self.manifest.vm_image.as_ref().map(|image| image.path())
+            }
+        };
+
+        let mut envs = vec![(
+            "OSDK_SERIAL_LOG",
+            config
+                .work_dir
+                .join("qemu.log")
+                .to_string_lossy()
+                .to_string(),
+        )];
+        if let Some(image_path) = image_path {
+            envs.push((
+                "OSDK_IMAGE_PATH",
+                self.path.join(image_path).to_string_lossy().to_string(),
+            ));
+        }
+        envs
+    }
+
+    /// Returns the QEMU command that runs the bundle.
+    ///
+    /// It exits if the bundle cannot run with the configuration.
+    pub fn qemu_command(&self, config: &Config, action: ActionChoice) -> Command {
         match self.can_run_with_config(config, action) {
             Ok(()) => {}
             Err(msg) => {
-                error_msg!("{}", msg);
-                std::process::exit(Errno::RunBundle as _);
+                exit_with_error!(Errno::RunBundle, "{}", msg);
             }
         }
         let action = match action {
@@ -254,36 +470,26 @@ impl Bundle {
                 }
             }
             None => {
-                error_msg!("Failed to parse qemu args: {:#?}", &action.qemu.args);
-                process::exit(Errno::ParseMetadata as _);
+                exit_with_error!(
+                    Errno::ParseMetadata,
+                    "Failed to parse qemu args: {:#?}",
+                    &action.qemu.args
+                );
             }
         }
 
-        info!("Running QEMU: {:#?}", qemu_cmd);
-
-        let exit_status = qemu_cmd.status().unwrap();
-
-        // Find the QEMU output in "qemu.log", read it and check if it failed with a panic.
-        // Setting a QEMU log is required for source line stack trace because piping the output
-        // is less desirable when running QEMU with serial redirected to standard I/O.
-        let qemu_log_path = config.work_dir.join("qemu.log");
-        if let Ok(file) = std::fs::File::open(qemu_log_path) {
-            if let Some(aster_bin) = &self.manifest.aster_bin {
-                crate::util::trace_panic_from_log(file, self.path.join(aster_bin.path()));
-            }
+        // The second serial port (ttyS1) is written to the file without any processing, so the
+        // guest can transfer binary data to the host through it.
+        if let Some(ref serial_raw) = config.serial_raw {
+            let mut chardev = OsString::from("file,id=osdk-serial-raw,path=");
+            chardev.push(serial_raw);
+            qemu_cmd.arg("-chardev").arg(chardev);
+            qemu_cmd
+                .arg("-device")
+                .arg("isa-serial,index=1,chardev=osdk-serial-raw");
         }
 
-        // FIXME: When panicking it sometimes returns success, why?
-        if !exit_status.success() {
-            // FIXME: Exit code manipulation is not needed when using non-x86 QEMU
-            let qemu_exit_code = exit_status.code().unwrap();
-            let kernel_exit_code = qemu_exit_code >> 1;
-            match kernel_exit_code {
-                0x10 /*ostd::QemuExitCode::Success*/ => { std::process::exit(0); },
-                0x20 /*ostd::QemuExitCode::Failed*/ => { std::process::exit(1); },
-                _ /* unknown, e.g., a triple fault */ => { std::process::exit(2) },
-            }
-        }
+        qemu_cmd
     }
 
     /// Move the vm_image into the bundle.
@@ -311,3 +517,59 @@ impl Bundle {
         std::fs::write(manifest_file_path, manifest_file_content).unwrap();
     }
 }
+
+/// Prints the summary of the missing system calls reported in the audit mode, if any.
+fn print_missing_syscalls(missing_syscalls: &MissingSyscalls) {
+    if missing_syscalls.is_empty() {
+        return;
+    }
+    warn_msg!(
+        "The kernel reported {} missing system call(s)",
+        missing_syscalls.len()
+    );
+    println!("{}", missing_syscalls.render());
+}
+
+/// Interprets the exit status of QEMU as the result reported by the kernel.
+///
+/// On failure, it returns 1 if the kernel reports a failure and 2 if the
+/// failure is unknown.
+pub fn kernel_exit_result(exit_status: ExitStatus) -> Result<(), i32> {
+    if exit_status.success() {
+        return Ok(());
+    }
+
+    // FIXME: Exit code manipulation is not needed when using non-x86 QEMU
+    let Some(qemu_exit_code) = exit_status.code() else {
+        // Killed by a signal.
+        return Err(2);
+    };
+    let kernel_exit_code = qemu_exit_code >> 1;
+    match kernel_exit_code {
+        0x10 /*ostd::QemuExitCode::Success*/ => Ok(()),
+        0x20 /*ostd::QemuExitCode::Failed*/ => Err(1),
+        _ /* unknown, e.g., a triple fault */ => Err(2),
+    }
+}
+
+/// Returns the suggestion if QEMU is not found.
+pub fn qemu_install_help(config: &Config) -> String {
+    format!(
+        "install QEMU (e.g., `{}`) or set `qemu.path` in the manifest",
+        config.target_arch.system_qemu()
+    )
+}
+
+/// Runs a hook command with the shell and returns whether it succeeds.
+fn run_hook(hook: &str, config: &Config, envs: &[(&str, String)]) -> bool {
+    let mut hook_cmd = Command::new("sh");
+    hook_cmd
+        .arg("-c")
+        .arg(hook)
+        .current_dir(&config.work_dir)
+        .envs(envs.iter().cloned());
+
+    info!("Running hook: {:#?}", hook_cmd);
+
+    hook_cmd.status().is_ok_and(|status| status.success())
+}
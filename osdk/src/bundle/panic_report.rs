@@ -0,0 +1,289 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Parsing and printing the panic reports emitted by the kernel.
+//!
+//! See `ostd/src/panic/report.rs` for the format of the report. On x86-64,
+//! the report is written to a dedicated QEMU debug console, which OSDK
+//! redirects to a file in the working directory. Otherwise, the report is
+//! printed to the console and OSDK finds it in the QEMU log.
+
+use std::{fmt::Write, path::Path, process::Command};
+
+const BEGIN_MARKER: &str = "---- BEGIN PANIC REPORT v1 ----";
+const END_MARKER: &str = "---- END PANIC REPORT ----";
+
+/// The number of the registers printed in a line.
+const REGS_PER_LINE: usize = 4;
+
+/// A panic report of the kernel.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PanicReport {
+    message: Vec<String>,
+    location: Option<String>,
+    cpu: Option<String>,
+    registers: Vec<(String, String)>,
+    frames: Vec<Frame>,
+    logs: Vec<String>,
+    /// Whether the report is cut off, e.g., if the kernel panics again.
+    is_truncated: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Frame {
+    pc: u64,
+    /// The symbol resolved by the kernel.
+    kernel_symbol: Option<String>,
+}
+
+impl PanicReport {
+    /// Parses the last report in the output of the kernel.
+    ///
+    /// Returns `None` if there is no report.
+    pub fn parse(output: &str) -> Option<Self> {
+        let start = output.rfind(BEGIN_MARKER)? + BEGIN_MARKER.len();
+
+        let mut report = Self {
+            is_truncated: true,
+            ..Self::default()
+        };
+        for line in output[start..].lines() {
+            // The lines may be ended with "\r\n" on the console.
+            let line = line.trim_end();
+            if line == END_MARKER {
+                report.is_truncated = false;
+                break;
+            }
+
+            let (keyword, value) = line.split_once(' ').unwrap_or((line, ""));
+            match keyword {
+                "message" => report.message.push(value.to_owned()),
+                "location" => report.location = Some(value.to_owned()),
+                "cpu" => report.cpu = Some(value.to_owned()),
+                "reg" => {
+                    if let Some((name, value)) = value.split_once(' ') {
+                        report.registers.push((name.to_owned(), value.to_owned()));
+                    }
+                }
+                "frame" => {
+                    let (pc, symbol) = value.split_once(' ').unwrap_or((value, ""));
+                    let Some(pc) = parse_hex(pc) else {
+                        continue;
+                    };
+                    report.frames.push(Frame {
+                        pc,
+                        kernel_symbol: (!symbol.is_empty()).then(|| symbol.to_owned()),
+                    });
+                }
+                "log" => report.logs.push(value.to_owned()),
+                // Ignore the unknown lines, which may be interleaved console output.
+                _ => {}
+            }
+        }
+
+        Some(report)
+    }
+
+    /// Returns the first line of the panic message.
+    pub fn summary(&self) -> &str {
+        self.message.first().map_or("", String::as_str)
+    }
+
+    /// Returns the signature of the panic, which consists of the first line
+    /// of the message and the location.
+    ///
+    /// The panics caused by the same bug usually have the same signature,
+    /// while the registers, the stack traces and the logs may differ.
+    pub fn signature(&self) -> String {
+        match &self.location {
+            Some(location) => format!("{} at {}", self.summary(), location),
+            None => self.summary().to_owned(),
+        }
+    }
+
+    /// Renders the report in a human-readable form.
+    ///
+    /// The addresses in the stack trace are resolved to the functions and
+    /// the source lines with the debug information in the kernel ELF. If
+    /// that fails, the symbols resolved by the kernel are used.
+    pub fn render(&self, elf_path: &Path) -> String {
+        let locations = symbolize(elf_path, &self.frames);
+
+        let mut output = String::new();
+        match &self.cpu {
+            Some(cpu) => writeln!(output, "The kernel panicked on CPU {}:", cpu),
+            None => writeln!(output, "The kernel panicked:"),
+        }
+        .unwrap();
+        for line in &self.message {
+            writeln!(output, "    {}", line).unwrap();
+        }
+        if let Some(location) = &self.location {
+            writeln!(output, "    at {}", location).unwrap();
+        }
+
+        if !self.registers.is_empty() {
+            writeln!(output, "\nRegisters:").unwrap();
+            for regs in self.registers.chunks(REGS_PER_LINE) {
+                output.push_str("   ");
+                for (name, value) in regs {
+                    write!(output, " {:>3} {:>18}", name, value).unwrap();
+                }
+                output.push('\n');
+            }
+        }
+
+        if !self.frames.is_empty() {
+            writeln!(output, "\nStack trace:").unwrap();
+        }
+        for (i, frame) in self.frames.iter().enumerate() {
+            let location = locations.get(i).and_then(Option::as_ref);
+            let function = location
+                .map(|(function, _)| function.as_str())
+                .or(frame.kernel_symbol.as_deref())
+                .unwrap_or("??");
+            writeln!(
+                output,
+                "    ({:>3}) {:#018x} in {}",
+                i + 1,
+                frame.pc,
+                function
+            )
+            .unwrap();
+            if let Some((_, source)) = location {
+                writeln!(output, "                                at {}", source).unwrap();
+            }
+        }
+
+        if !self.logs.is_empty() {
+            writeln!(output, "\nRecent logs:").unwrap();
+            for log in &self.logs {
+                writeln!(output, "    {}", log).unwrap();
+            }
+        }
+
+        if self.is_truncated {
+            writeln!(output, "\nThe report is truncated.").unwrap();
+        }
+        output
+    }
+}
+
+fn parse_hex(s: &str) -> Option<u64> {
+    u64::from_str_radix(s.strip_prefix("0x")?, 16).ok()
+}
+
+/// Resolves the function names and the source lines of the frames with
+/// `addr2line`.
+///
+/// The result is empty if `addr2line` is not available.
+fn symbolize(elf_path: &Path, frames: &[Frame]) -> Vec<Option<(String, String)>> {
+    if frames.is_empty() {
+        return Vec::new();
+    }
+
+    // The return address may be the start of the next function if the call
+    // is the last instruction, so the address before it is used.
+    let output = Command::new("addr2line")
+        .arg("--exe")
+        .arg(elf_path)
+        .args(["--functions", "--demangle"])
+        .args(
+            frames
+                .iter()
+                .map(|frame| format!("{:#x}", frame.pc.saturating_sub(1))),
+        )
+        .output();
+    let Ok(output) = output else {
+        return Vec::new();
+    };
+    if !output.status.success() {
+        return Vec::new();
+    }
+
+    // Each address is resolved to two lines: the function and the source line.
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<_> = stdout.lines().collect();
+    lines
+        .chunks(2)
+        .map(|chunk| match chunk {
+            [function, source] if *function != "??" => {
+                Some((function.to_string(), source.to_string()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_report() {
+        let output = "\
+[    1.234] ERROR: Uncaught panic:\r
+---- BEGIN PANIC REPORT v1 ----\r
+message called `Option::unwrap()` on a `None` value\r
+message second line\r
+location kernel/src/lib.rs:42:5\r
+cpu 1\r
+reg rax 0x0\r
+reg rdx 0x1f\r
+frame 0xffffffff88001234 aster_nix::main+0x34/0x80\r
+frame 0xffffffff88005678\r
+log ERROR: Uncaught panic:\r
+---- END PANIC REPORT ----\r
+";
+
+        let report = PanicReport::parse(output).unwrap();
+        assert_eq!(
+            report,
+            PanicReport {
+                message: vec![
+                    "called `Option::unwrap()` on a `None` value".to_owned(),
+                    "second line".to_owned()
+                ],
+                location: Some("kernel/src/lib.rs:42:5".to_owned()),
+                cpu: Some("1".to_owned()),
+                registers: vec![
+                    ("rax".to_owned(), "0x0".to_owned()),
+                    ("rdx".to_owned(), "0x1f".to_owned())
+                ],
+                frames: vec![
+                    Frame {
+                        pc: 0xffffffff88001234,
+                        kernel_symbol: Some("aster_nix::main+0x34/0x80".to_owned()),
+                    },
+                    Frame {
+                        pc: 0xffffffff88005678,
+                        kernel_symbol: None,
+                    },
+                ],
+                logs: vec!["ERROR: Uncaught panic:".to_owned()],
+                is_truncated: false,
+            }
+        );
+        assert_eq!(
+            report.summary(),
+            "called `Option::unwrap()` on a `None` value"
+        );
+        assert_eq!(
+            report.signature(),
+            "called `Option::unwrap()` on a `None` value at kernel/src/lib.rs:42:5"
+        );
+    }
+
+    #[test]
+    fn parse_truncated_report() {
+        let output = "---- BEGIN PANIC REPORT v1 ----\nmessage oops\nframe 0x1000 foo\n";
+
+        let report = PanicReport::parse(output).unwrap();
+        assert!(report.is_truncated);
+        assert_eq!(report.frames.len(), 1);
+    }
+
+    #[test]
+    fn parse_no_report() {
+        assert!(PanicReport::parse("Hello, world!\n").is_none());
+    }
+}
@@ -0,0 +1,133 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Summarizing the missing system calls reported by the kernel.
+//!
+//! See `kernel/src/syscall/audit.rs` for the format of the reports, which
+//! are printed to the console only if the kernel command line contains
+//! `syscall.audit`. OSDK finds them in the QEMU log.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+const MARKER: &str = "[missing-syscall] ";
+
+/// The missing system calls reported by the kernel.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MissingSyscalls {
+    syscalls: BTreeMap<u64, MissingSyscall>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct MissingSyscall {
+    /// The names of the programs that make the system call.
+    callers: BTreeSet<String>,
+    /// The arguments of the first reported call.
+    args: String,
+}
+
+impl MissingSyscalls {
+    /// Parses the reports in the output of the kernel.
+    pub fn parse(output: &str) -> Self {
+        let mut missing = Self::default();
+        for line in output.lines() {
+            let Some(start) = line.find(MARKER) else {
+                continue;
+            };
+            let report = line[start + MARKER.len()..].trim_end();
+
+            // The name of the program is the last field and may contain spaces.
+            let (fields, comm) = report.split_once(" comm=").unwrap_or((report, ""));
+            let mut nr = None;
+            let mut args = "";
+            for field in fields.split(' ') {
+                match field.split_once('=') {
+                    Some(("nr", value)) => nr = value.parse().ok(),
+                    Some(("args", value)) => args = value,
+                    _ => {}
+                }
+            }
+            let Some(nr) = nr else {
+                continue;
+            };
+
+            let syscall = missing
+                .syscalls
+                .entry(nr)
+                .or_insert_with(|| MissingSyscall {
+                    callers: BTreeSet::new(),
+                    args: args.to_owned(),
+                });
+            syscall.callers.insert(comm.to_owned());
+        }
+        missing
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.syscalls.is_empty()
+    }
+
+    /// Returns the number of the distinct missing system calls.
+    pub fn len(&self) -> usize {
+        self.syscalls.len()
+    }
+
+    /// Renders the summary, in which the system calls are sorted by the
+    /// number of the programs that make them.
+    pub fn render(&self) -> String {
+        let mut syscalls: Vec<_> = self.syscalls.iter().collect();
+        syscalls.sort_by_key(|(nr, syscall)| (std::cmp::Reverse(syscall.callers.len()), **nr));
+
+        let mut output = String::new();
+        writeln!(output, "{:>6}  {:<40}  ARGS", "NR", "CALLERS").unwrap();
+        for (nr, syscall) in syscalls {
+            let callers: Vec<_> = syscall
+                .callers
+                .iter()
+                .map(|caller| if caller.is_empty() { "?" } else { caller })
+                .collect();
+            writeln!(
+                output,
+                "{:>6}  {:<40}  {}",
+                nr,
+                callers.join(", "),
+                syscall.args
+            )
+            .unwrap();
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_and_render() {
+        let output = "\
+[kernel] Spawn init thread\r
+[missing-syscall] nr=441 pid=1 args=0x3,0x0,0x0,0x0,0x0,0x0 comm=sh\r
+[   1.234] WARN: Unimplemented syscall number: 441\r
+~ # [missing-syscall] nr=441 pid=5 args=0x4,0x0,0x0,0x0,0x0,0x0 comm=my prog\r
+[missing-syscall] nr=332 pid=5 args=0x1,0x2,0x0,0x0,0x0,0x0 comm=ls\r
+[missing-syscall] nr=bad comm=ls\r
+";
+
+        let missing = MissingSyscalls::parse(output);
+        assert_eq!(missing.len(), 2);
+        assert_eq!(
+            missing.render(),
+            "    NR  CALLERS                                   ARGS
+   441  my prog, sh                               0x3,0x0,0x0,0x0,0x0,0x0
+   332  ls                                        0x1,0x2,0x0,0x0,0x0,0x0
+"
+        );
+    }
+
+    #[test]
+    fn parse_no_reports() {
+        assert!(MissingSyscalls::parse("Hello, world!\n").is_empty());
+    }
+}
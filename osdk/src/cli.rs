@@ -2,26 +2,34 @@
 
 use std::path::PathBuf;
 
-use clap::{crate_version, Args, Parser, ValueEnum};
+use clap::{crate_version, Args, Parser, Subcommand, ValueEnum};
 
 use crate::{
     arch::Arch,
     commands::{
-        execute_build_command, execute_debug_command, execute_forwarded_command,
-        execute_forwarded_command_on_each_crate, execute_new_command, execute_profile_command,
-        execute_run_command, execute_test_command,
+        enable_offline_mode, execute_build_command, execute_burn_command,
+        execute_check_config_command, execute_crash_test_command, execute_debug_command,
+        execute_deploy_command, execute_forwarded_command, execute_forwarded_command_on_each_crate,
+        execute_fuzz_command, execute_measure_command, execute_metrics_collect_command,
+        execute_metrics_compare_command, execute_new_command, execute_profile_command,
+        execute_run_command, execute_sbom_command, execute_scenarios, execute_test_command,
     },
     config::{
         manifest::{ProjectType, TomlManifest},
-        scheme::{BootMethod, BootProtocol},
+        scheme::{BootMethod, BootProtocol, Debugger, DirectBootProtocol, DiskImageFormat},
         Config,
     },
+    diagnostic::{set_message_format, MessageFormat},
 };
 
 use linux_bzimage_builder::PayloadEncoding;
 
 pub fn main() {
     let load_config = |common_args: &CommonArgs| {
+        set_message_format(common_args.message_format);
+        if common_args.offline {
+            enable_offline_mode();
+        }
         let manifest = TomlManifest::load();
         let scheme = manifest.get_scheme(common_args.scheme.as_ref());
         Config::new(scheme, common_args)
@@ -36,26 +44,49 @@ pub fn main() {
             execute_build_command(&load_config(&build_args.common_args), build_args);
         }
         OsdkSubcommand::Run(run_args) => {
-            execute_run_command(
-                &load_config(&run_args.common_args),
-                run_args.gdb_server.as_deref(),
-            );
+            let config = load_config(&run_args.common_args);
+            if run_args.scenarios.is_empty() {
+                execute_run_command(&config, run_args.gdb_server.as_deref());
+            } else {
+                execute_scenarios(&config, &run_args.scenarios, run_args.bless);
+            }
+        }
+        OsdkSubcommand::Deploy(deploy_args) => {
+            execute_deploy_command(&load_config(&deploy_args.common_args), deploy_args);
+        }
+        OsdkSubcommand::Burn(burn_args) => {
+            execute_burn_command(&load_config(&burn_args.common_args), burn_args);
         }
         OsdkSubcommand::Debug(debug_args) => {
-            execute_debug_command(
-                &load_config(&debug_args.common_args).run.build.profile,
-                debug_args,
-            );
+            execute_debug_command(&load_config(&debug_args.common_args), debug_args);
         }
         OsdkSubcommand::Profile(profile_args) => {
-            execute_profile_command(
-                &load_config(&profile_args.common_args).run.build.profile,
-                profile_args,
-            );
+            execute_profile_command(&load_config(&profile_args.common_args), profile_args);
         }
         OsdkSubcommand::Test(test_args) => {
             execute_test_command(&load_config(&test_args.common_args), test_args);
         }
+        OsdkSubcommand::Fuzz(fuzz_args) => {
+            execute_fuzz_command(&load_config(&fuzz_args.common_args), fuzz_args);
+        }
+        OsdkSubcommand::CrashTest(crash_test_args) => {
+            execute_crash_test_command(&load_config(&crash_test_args.common_args), crash_test_args);
+        }
+        OsdkSubcommand::Sbom(sbom_args) => {
+            execute_sbom_command(&load_config(&sbom_args.common_args), sbom_args);
+        }
+        OsdkSubcommand::Measure(measure_args) => {
+            execute_measure_command(&load_config(&measure_args.common_args), measure_args);
+        }
+        OsdkSubcommand::Metrics(MetricsArgs { command }) => match command {
+            MetricsCommand::Collect(args) => {
+                execute_metrics_collect_command(&load_config(&args.common_args), args);
+            }
+            MetricsCommand::Compare(args) => execute_metrics_compare_command(args),
+        },
+        OsdkSubcommand::CheckConfig(args) => {
+            execute_check_config_command(&load_config(&args.common_args));
+        }
         OsdkSubcommand::Check(args) => {
             execute_forwarded_command_on_each_crate("check", &args.args, true)
         }
@@ -88,12 +119,28 @@ pub enum OsdkSubcommand {
     Build(BuildArgs),
     #[command(about = "Run the kernel with a VMM")]
     Run(RunArgs),
-    #[command(about = "Debug a remote target via GDB")]
+    #[command(about = "Deploy the kernel to a remote machine or a TFTP root")]
+    Deploy(DeployArgs),
+    #[command(about = "Write the bootable ISO image to a removable drive")]
+    Burn(BurnArgs),
+    #[command(about = "Debug the kernel in QEMU or a remote target via GDB")]
     Debug(DebugArgs),
     #[command(about = "Profile a remote GDB debug target to collect stack traces for flame graph")]
     Profile(ProfileArgs),
     #[command(about = "Execute kernel mode unit test by starting a VMM")]
     Test(TestArgs),
+    #[command(about = "Fuzz the system calls or the packet parsers with coverage guidance")]
+    Fuzz(FuzzArgs),
+    #[command(about = "Stop the kernel at random times and check the disk images for corruption")]
+    CrashTest(CrashTestArgs),
+    #[command(about = "Generate the software bill of materials (SBOM) of the built image")]
+    Sbom(SbomArgs),
+    #[command(about = "Compute the expected measurements of the built image for attestation")]
+    Measure(MeasureArgs),
+    #[command(about = "Collect the performance metrics or compare them for regressions")]
+    Metrics(MetricsArgs),
+    #[command(about = "Validate the OSDK manifest and print the effective configuration")]
+    CheckConfig(CheckConfigArgs),
     #[command(about = "Check a local package and all of its dependencies for errors")]
     Check(ForwardedArguments),
     #[command(about = "Checks a package to catch common mistakes and improve your Rust code")]
@@ -167,6 +214,18 @@ pub struct BuildArgs {
         value_name = "DIR"
     )]
     pub output: Option<PathBuf>,
+    #[arg(
+        long = "disk-image",
+        help = "Also produce a GPT disk image with an EFI system partition that boots the kernel",
+        value_name = "FORMAT"
+    )]
+    pub disk_image: Option<DiskImageFormat>,
+    #[arg(
+        long = "kcov",
+        help = "Instrument the kernel to collect the coverage of each thread via /sys/kernel/debug/kcov",
+        default_value_t
+    )]
+    pub kcov: bool,
     #[command(flatten)]
     pub common_args: CommonArgs,
 }
@@ -185,18 +244,211 @@ pub struct RunArgs {
         default_missing_value = ""
     )]
     pub gdb_server: Option<String>,
+    #[arg(
+        long = "scenario",
+        help = "Run the scenario described by the TOML file and compare the output \
+                of the kernel with the golden file\n\
+                This option can be specified multiple times to run multiple scenarios",
+        value_name = "FILE",
+        conflicts_with = "gdb_server"
+    )]
+    pub scenarios: Vec<PathBuf>,
+    #[arg(
+        long,
+        help = "Update the golden files of the scenarios with the output of the kernel",
+        requires = "scenarios"
+    )]
+    pub bless: bool,
+    #[command(flatten)]
+    pub common_args: CommonArgs,
+}
+
+#[derive(Debug, Parser)]
+pub struct DeployArgs {
+    #[arg(
+        long,
+        help = "The deployment target, `ssh://[USER@]HOST:DIR` for a remote directory \
+                or `tftp://DIR` for a local TFTP root directory",
+        value_name = "URI"
+    )]
+    pub target: String,
+    #[arg(
+        long = "boot-entry",
+        help = "Also install a boot entry, i.e., a GRUB `custom.cfg` for SSH targets \
+                or an iPXE script for TFTP targets"
+    )]
+    pub boot_entry: bool,
+    #[arg(
+        long = "with-image",
+        help = "Also deploy the bootable VM image if the boot method produces one"
+    )]
+    pub with_image: bool,
+    #[arg(
+        long = "ipmi-host",
+        help = "Power-cycle the target with `ipmitool` after deploying\n\
+                The credentials are read from the `IPMI_USER` and `IPMI_PASSWORD` environment variables",
+        value_name = "HOST",
+        conflicts_with = "power_cycle_cmd"
+    )]
+    pub ipmi_host: Option<String>,
+    #[arg(
+        long = "power-cycle-cmd",
+        help = "A shell command to power-cycle the target after deploying",
+        value_name = "CMD"
+    )]
+    pub power_cycle_cmd: Option<String>,
+    #[command(flatten)]
+    pub common_args: CommonArgs,
+}
+
+#[derive(Debug, Parser)]
+pub struct BurnArgs {
+    #[arg(
+        long,
+        help = "The block device of the whole drive, e.g., `/dev/sdb`",
+        value_name = "PATH"
+    )]
+    pub device: PathBuf,
+    #[arg(long, short = 'y', help = "Do not ask for confirmation before writing")]
+    pub yes: bool,
+    #[arg(
+        long,
+        help = "Also write to a drive that is not removable or is unusually large"
+    )]
+    pub force: bool,
+    #[arg(
+        long = "no-verify",
+        help = "Do not read the image back to verify it after writing"
+    )]
+    pub no_verify: bool,
+    #[command(flatten)]
+    pub common_args: CommonArgs,
+}
+
+#[derive(Debug, Parser)]
+pub struct SbomArgs {
+    #[arg(
+        long,
+        help = "The format of the SBOM document",
+        default_value = "cyclonedx"
+    )]
+    pub format: SbomFormat,
+    #[arg(
+        long,
+        short = 'o',
+        help = "The path to the output SBOM document \
+                [default: target/osdk/<CRATE>.cdx.json or target/osdk/<CRATE>.spdx.json]",
+        value_name = "PATH"
+    )]
+    pub output: Option<PathBuf>,
+    #[command(flatten)]
+    pub common_args: CommonArgs,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum SbomFormat {
+    /// CycloneDX 1.5 in JSON
+    Cyclonedx,
+    /// SPDX 2.3 in JSON
+    Spdx,
+}
+
+#[derive(Debug, Parser)]
+pub struct MeasureArgs {
+    #[arg(
+        long,
+        short = 'o',
+        help = "The path to the output JSON file [default: target/osdk/<CRATE>.measurements.json]",
+        value_name = "PATH"
+    )]
+    pub output: Option<PathBuf>,
     #[command(flatten)]
     pub common_args: CommonArgs,
 }
 
+#[derive(Debug, Parser)]
+pub struct MetricsArgs {
+    #[command(subcommand)]
+    pub command: MetricsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MetricsCommand {
+    #[command(about = "Collect the metrics files and the benchmark results into a metrics file")]
+    Collect(Box<MetricsCollectArgs>),
+    #[command(about = "Compare two metrics files and fail if any metric regresses")]
+    Compare(MetricsCompareArgs),
+}
+
+#[derive(Debug, Parser)]
+pub struct MetricsCollectArgs {
+    #[arg(
+        name = "FILES",
+        help = "The metrics files or the benchmark result files (`result_<suite>-<job>.json`)",
+        required = true
+    )]
+    pub files: Vec<PathBuf>,
+    #[arg(
+        long,
+        short = 'o',
+        help = "The path to the output metrics file",
+        value_name = "PATH"
+    )]
+    pub output: PathBuf,
+    #[arg(
+        long = "bench-dir",
+        help = "The directory of the benchmark configurations",
+        value_name = "DIR",
+        default_value = "test/benchmark"
+    )]
+    pub bench_dir: PathBuf,
+    #[command(flatten)]
+    pub common_args: CommonArgs,
+}
+
+#[derive(Debug, Parser)]
+pub struct MetricsCompareArgs {
+    #[arg(name = "BASE", help = "The metrics file to compare against")]
+    pub base: PathBuf,
+    #[arg(name = "NEW", help = "The metrics file to check for regressions")]
+    pub new: PathBuf,
+    #[arg(
+        long = "threshold",
+        help = "The percentage by which a metric may get worse without being a regression",
+        value_name = "PERCENT",
+        default_value_t = 5.0
+    )]
+    pub threshold: f64,
+    #[arg(
+        long = "metric-threshold",
+        help = "Override the threshold of the metric, or of the metrics starting with the prefix \
+                if NAME ends with `*`",
+        value_name = "NAME=PERCENT"
+    )]
+    pub metric_thresholds: Vec<String>,
+}
+
 #[derive(Debug, Parser)]
 pub struct DebugArgs {
     #[arg(
         long,
-        help = "Specify the address of the remote target",
-        default_value = ".osdk-gdb-socket"
+        help = "Specify the address of the GDB server, which is either a path of a UNIX domain socket \
+                or `[IP]:PORT` [default: .osdk-gdb-socket]",
+        value_name = "ADDR"
     )]
-    pub remote: String,
+    pub remote: Option<String>,
+    #[arg(
+        long,
+        help = "Attach to a GDB server that is already running instead of launching QEMU"
+    )]
+    pub attach: bool,
+    #[arg(
+        long,
+        help = "The debugger to attach to the GDB server, or `none` to wait for one to attach manually \
+                [default: gdb]",
+        value_name = "DEBUGGER"
+    )]
+    pub debugger: Option<Debugger>,
     #[command(flatten)]
     pub common_args: CommonArgs,
 }
@@ -225,6 +477,24 @@ pub struct ProfileArgs {
         conflicts_with = "interval"
     )]
     pub parse: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Build and run the kernel with the in-kernel sampling profiler instead of attaching to a GDB server",
+        conflicts_with = "remote",
+        conflicts_with = "samples",
+        conflicts_with = "interval",
+        conflicts_with = "parse"
+    )]
+    pub sampler: bool,
+    #[arg(
+        long,
+        help = "The number of samples per second on each CPU with the in-kernel sampling profiler",
+        value_name = "HZ",
+        default_value = "100",
+        value_parser = clap::builder::RangedU64ValueParser::<u64>::new().range(1..=1000),
+        requires = "sampler"
+    )]
+    pub freq: u64,
     #[command(flatten)]
     pub out_args: DebugProfileOutArgs,
     #[command(flatten)]
@@ -326,6 +596,205 @@ pub struct TestArgs {
         help = "Only run tests containing this string in their names"
     )]
     pub test_name: Option<String>,
+    #[arg(
+        long = "matrix",
+        help = "Run the tests across a matrix of boot options described in the TOML file\n\
+                Each combination of the `kcmd_args` and the `qemu_args` variants boots the kernel once",
+        value_name = "FILE"
+    )]
+    pub matrix: Option<PathBuf>,
+    #[arg(
+        long = "jobs",
+        short = 'j',
+        help = "Run the tests in N QEMU instances in parallel, each of which runs a shard of the tests",
+        value_name = "N",
+        default_value_t = 1,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        conflicts_with = "matrix"
+    )]
+    pub jobs: usize,
+    #[arg(
+        long = "timeout",
+        help = "Kill the QEMU instance and mark the test failed if a test runs for more than SECONDS",
+        value_name = "SECONDS",
+        conflicts_with = "matrix"
+    )]
+    pub timeout: Option<u64>,
+    #[arg(
+        long = "junit",
+        help = "Write the test results to the file as a JUnit XML report",
+        value_name = "FILE",
+        conflicts_with = "matrix"
+    )]
+    pub junit: Option<PathBuf>,
+    #[arg(
+        long = "metrics",
+        help = "Write the boot time and the test durations to the file as a metrics file",
+        value_name = "FILE",
+        conflicts_with = "matrix"
+    )]
+    pub metrics: Option<PathBuf>,
+    #[command(flatten)]
+    pub common_args: CommonArgs,
+}
+
+#[derive(Debug, Parser)]
+pub struct FuzzArgs {
+    #[arg(
+        long = "target",
+        help = "The interface of the kernel that the inputs are fed to",
+        value_enum,
+        default_value = "syscall"
+    )]
+    pub target: FuzzTarget,
+    #[arg(
+        long = "corpus",
+        help = "The directory of the inputs that increase the coverage\n\
+                The inputs in it are loaded at start, and the new ones are saved to it",
+        value_name = "DIR",
+        default_value = "fuzz/corpus"
+    )]
+    pub corpus: PathBuf,
+    #[arg(
+        long = "crashes",
+        help = "The directory to save the inputs that crash or hang the kernel",
+        value_name = "DIR",
+        default_value = "fuzz/crashes"
+    )]
+    pub crashes: PathBuf,
+    #[arg(
+        long = "runs",
+        help = "Stop after running N inputs, instead of running until interrupted",
+        value_name = "N"
+    )]
+    pub runs: Option<u64>,
+    #[arg(
+        long = "seed",
+        help = "The seed of the random mutations, which is derived from the time by default"
+    )]
+    pub seed: Option<u64>,
+    #[arg(
+        long = "timeout",
+        help = "Treat an input as a hang if it runs for more than SECONDS",
+        value_name = "SECONDS",
+        default_value_t = 10
+    )]
+    pub timeout: u64,
+    #[arg(
+        long = "minimize",
+        help = "Minimize the crashing input in the file instead of fuzzing",
+        value_name = "FILE",
+        conflicts_with_all = ["runs", "seed", "no_minimize"]
+    )]
+    pub minimize: Option<PathBuf>,
+    #[arg(
+        long = "no-minimize",
+        help = "Save the crashing inputs found by fuzzing without minimizing them"
+    )]
+    pub no_minimize: bool,
+    #[command(flatten)]
+    pub common_args: CommonArgs,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum FuzzTarget {
+    /// Sequences of system calls, which are run by a user program
+    Syscall,
+    /// Netlink packets, which are parsed by the network stack
+    Packet,
+}
+
+#[derive(Debug, Parser)]
+pub struct CrashTestArgs {
+    #[arg(
+        long = "image",
+        help = "The disk image that the workload writes to, as configured in the QEMU arguments",
+        value_name = "PATH"
+    )]
+    pub image: PathBuf,
+    #[arg(
+        long = "checker",
+        help = "The shell command that checks the disk image and fails if it is corrupted\n\
+                The path of the disk image is passed as `$1`, e.g., `e2fsck -f -n \"$1\"`",
+        value_name = "COMMAND"
+    )]
+    pub checker: String,
+    #[arg(
+        long = "recover-args",
+        require_equals = true,
+        help = "Boot the kernel again with the init arguments to recover the disk image \
+                before checking it, e.g., by mounting the filesystem",
+        value_name = "ARGS"
+    )]
+    pub recover_args: Option<String>,
+    #[arg(
+        long = "rounds",
+        help = "The number of the times to stop the kernel",
+        value_name = "N",
+        default_value_t = 10
+    )]
+    pub rounds: u64,
+    #[arg(
+        long = "ready",
+        help = "Start counting the delay after a line of the output matches the pattern, \
+                instead of after QEMU starts",
+        value_name = "REGEX"
+    )]
+    pub ready: Option<String>,
+    #[arg(
+        long = "min-delay",
+        help = "The minimum time to run the workload before stopping the kernel",
+        value_name = "MILLISECONDS",
+        default_value_t = 0
+    )]
+    pub min_delay: u64,
+    #[arg(
+        long = "max-delay",
+        help = "The maximum time to run the workload before stopping the kernel",
+        value_name = "MILLISECONDS",
+        default_value_t = 10000
+    )]
+    pub max_delay: u64,
+    #[arg(
+        long = "stop",
+        help = "How the kernel is stopped",
+        value_enum,
+        default_value = "kill"
+    )]
+    pub stop: StopMethod,
+    #[arg(
+        long = "seed",
+        help = "The seed of the random delays, which is derived from the time by default"
+    )]
+    pub seed: Option<u64>,
+    #[arg(
+        long = "timeout",
+        help = "Fail if the workload is not ready or the recovery does not finish in SECONDS",
+        value_name = "SECONDS",
+        default_value_t = 300
+    )]
+    pub timeout: u64,
+    #[arg(
+        long = "failures",
+        help = "The directory to save the disk image and the outputs of a failed round",
+        value_name = "DIR",
+        default_value = "crash-test/failures"
+    )]
+    pub failures: PathBuf,
+    #[command(flatten)]
+    pub common_args: CommonArgs,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum StopMethod {
+    /// Kill QEMU, which drops the in-flight requests
+    Kill,
+    /// Stop the VM and quit QEMU via QMP, which completes the in-flight requests
+    Qmp,
+}
+
+#[derive(Debug, Parser)]
+pub struct CheckConfigArgs {
     #[command(flatten)]
     pub common_args: CommonArgs,
 }
@@ -395,11 +864,27 @@ pub struct CommonArgs {
     pub strip_elf: bool,
     #[arg(
         long = "target-arch",
+        visible_alias = "arch",
         value_name = "ARCH",
         help = "The architecture to build for",
         global = true
     )]
     pub target_arch: Option<Arch>,
+    #[arg(
+        long = "message-format",
+        help = "The output format of the diagnostic messages",
+        value_name = "FORMAT",
+        default_value = "human",
+        global = true
+    )]
+    pub message_format: MessageFormat,
+    #[arg(
+        long,
+        help = "Build without accessing the network\n\
+                All the required components must be available locally",
+        global = true
+    )]
+    pub offline: bool,
     #[arg(
         long = "scheme",
         help = "Select the specific configuration scheme provided in the OSDK manifest",
@@ -423,7 +908,12 @@ pub struct CommonArgs {
         global = true
     )]
     pub init_args: Vec<String>,
-    #[arg(long, help = "Path of initramfs", value_name = "PATH", global = true)]
+    #[arg(
+        long,
+        help = "Path of initramfs, which is either a CPIO archive or a directory to pack",
+        value_name = "PATH",
+        global = true
+    )]
     pub initramfs: Option<PathBuf>,
     #[arg(
         long = "boot-method",
@@ -432,6 +922,22 @@ pub struct CommonArgs {
         global = true
     )]
     pub boot_method: Option<BootMethod>,
+    #[arg(
+        long = "direct-boot-protocol",
+        help = "Protocol for booting the kernel if the boot method is `qemu-direct`",
+        value_name = "BOOT_PROTOCOL",
+        global = true
+    )]
+    pub direct_boot_protocol: Option<DirectBootProtocol>,
+    #[arg(
+        long = "boot-protocol",
+        help = "Boot the kernel with QEMU direct boot using the protocol\n\
+                A shorthand for `--boot-method qemu-direct --direct-boot-protocol <BOOT_PROTOCOL>`",
+        value_name = "BOOT_PROTOCOL",
+        conflicts_with_all = ["boot_method", "direct_boot_protocol"],
+        global = true
+    )]
+    pub boot_protocol: Option<DirectBootProtocol>,
     #[arg(
         long = "bootdev-append-options",
         help = "Additional QEMU `-drive` options for the boot device",
@@ -474,6 +980,14 @@ pub struct CommonArgs {
         global = true
     )]
     pub qemu_args: Vec<String>,
+    #[arg(
+        long = "serial-raw",
+        help = "Save the raw bytes written to the second serial port (ttyS1) of the guest\n\
+                Only supported on x86-64",
+        value_name = "PATH",
+        global = true
+    )]
+    pub serial_raw: Option<PathBuf>,
     #[arg(
         long = "encoding",
         help = "Denote the encoding format for kernel self-decompression",
@@ -0,0 +1,448 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The protocol between OSDK and the guest agent.
+//!
+//! The guest agent (see `test/apps/osdk_agent`) runs as the init process of
+//! the guest and executes the requests of OSDK one by one, so that a scenario
+//! can run multiple commands and transfer files without rebooting the kernel.
+//!
+//! The messages are exchanged over the console of the guest (i.e., the
+//! virtio-serial console or the serial port), which is the standard input and
+//! output of QEMU. Each message is a line of space-separated fields starting
+//! with [`MAGIC`], so that the responses can be told apart from the other
+//! output of the kernel. The binary fields (e.g., the arguments of a command
+//! and the content of a file) are hex-encoded, so the messages never contain
+//! control characters that the terminal or QEMU may interpret.
+//!
+//! The requests sent by OSDK are:
+//! - `RUN <ID> <ARG>...`: runs the command with the arguments;
+//! - `PUT <ID> <PATH> <MODE> <OFFSET> <DATA>`: writes the data to the file at
+//!   the offset, where the file is created with the mode (in octal) and
+//!   truncated if the offset is zero;
+//! - `GET <ID> <PATH>`: reads the file;
+//! - `QUIT <CODE>`: exits the agent with the exit code.
+//!
+//! The responses sent by the agent are:
+//! - `READY`: the agent is ready to accept requests;
+//! - `OUT <ID> <DATA>`: the standard output of the command or the content of
+//!   the file;
+//! - `ERR <ID> <DATA>`: the standard error of the command or the error message;
+//! - `EXIT <ID> <CODE>`: the request is finished with the exit code, which is
+//!   zero if a file request succeeds.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::util::{decode_output, to_hex};
+
+/// The prefix of the messages.
+const MAGIC: &str = "@osdk-agent";
+
+/// The maximum length of the data in a `PUT` request.
+const PUT_CHUNK_SIZE: usize = 2048;
+
+/// A request sent to the guest agent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Request {
+    Run {
+        id: u32,
+        argv: Vec<String>,
+    },
+    Put {
+        id: u32,
+        path: String,
+        mode: u32,
+        offset: usize,
+        data: Vec<u8>,
+    },
+    Get {
+        id: u32,
+        path: String,
+    },
+    Quit {
+        code: i32,
+    },
+}
+
+impl Request {
+    /// Encodes the request as a line.
+    fn encode(&self) -> String {
+        let fields = match self {
+            Self::Run { id, argv } => {
+                let mut fields = vec!["RUN".to_owned(), id.to_string()];
+                fields.extend(argv.iter().map(|arg| to_hex(arg.as_bytes())));
+                fields
+            }
+            Self::Put {
+                id,
+                path,
+                mode,
+                offset,
+                data,
+            } => vec![
+                "PUT".to_owned(),
+                id.to_string(),
+                to_hex(path.as_bytes()),
+                format!("{:o}", mode),
+                offset.to_string(),
+                to_hex(data),
+            ],
+            Self::Get { id, path } => {
+                vec!["GET".to_owned(), id.to_string(), to_hex(path.as_bytes())]
+            }
+            Self::Quit { code } => vec!["QUIT".to_owned(), code.to_string()],
+        };
+        format!("{} {}\n", MAGIC, fields.join(" "))
+    }
+}
+
+/// A response received from the guest agent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Response {
+    Ready,
+    Stdout { id: u32, data: Vec<u8> },
+    Stderr { id: u32, data: Vec<u8> },
+    Exit { id: u32, code: i32 },
+}
+
+impl Response {
+    /// Decodes the response in a line of the console output.
+    ///
+    /// Returns `None` if the line does not contain a valid response. The
+    /// response may be preceded by other output on the same line, e.g., if the
+    /// kernel prints a log message at the same time.
+    fn decode(line: &str) -> Option<Self> {
+        let (_, message) = line.split_once(MAGIC)?;
+        let mut fields = message.split_whitespace();
+        let response = match fields.next()? {
+            "READY" => Self::Ready,
+            "OUT" => Self::Stdout {
+                id: fields.next()?.parse().ok()?,
+                data: decode_hex(fields.next()?)?,
+            },
+            "ERR" => Self::Stderr {
+                id: fields.next()?.parse().ok()?,
+                data: decode_hex(fields.next()?)?,
+            },
+            "EXIT" => Self::Exit {
+                id: fields.next()?.parse().ok()?,
+                code: fields.next()?.parse().ok()?,
+            },
+            _ => return None,
+        };
+        fields.next().is_none().then_some(response)
+    }
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// The outcome of a request executed by the guest agent.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(super) struct CommandOutput {
+    pub(super) stdout: Vec<u8>,
+    pub(super) stderr: Vec<u8>,
+    pub(super) exit_code: i32,
+}
+
+/// A session with the guest agent.
+pub(super) struct AgentSession<W: Write> {
+    input: W,
+    lines: Receiver<String>,
+    /// The console output of the guest, excluding the responses of the agent.
+    console: String,
+    last_id: u32,
+    deadline: Option<Instant>,
+}
+
+impl<W: Write> AgentSession<W> {
+    /// Creates a session over the input and output of the guest console.
+    ///
+    /// All the requests of the session must be finished within the timeout.
+    pub(super) fn new(
+        input: W,
+        output: impl Read + Send + 'static,
+        timeout: Option<Duration>,
+    ) -> Self {
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            let mut output = BufReader::new(output);
+            let mut line = Vec::new();
+            while output.read_until(b'\n', &mut line).is_ok_and(|len| len > 0) {
+                if sender.send(decode_output(&line)).is_err() {
+                    break;
+                }
+                line.clear();
+            }
+        });
+
+        Self {
+            input,
+            lines,
+            console: String::new(),
+            last_id: 0,
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+        }
+    }
+
+    /// Restarts the timeout, so that the following requests of the session
+    /// must be finished within it.
+    pub(super) fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.deadline = timeout.map(|timeout| Instant::now() + timeout);
+    }
+
+    /// Waits until the agent is ready to accept requests.
+    pub(super) fn wait_ready(&mut self) -> Result<(), String> {
+        while self.recv()? != Response::Ready {}
+        Ok(())
+    }
+
+    /// Runs the command in the guest and waits for it to exit.
+    pub(super) fn run(&mut self, argv: &[String]) -> Result<CommandOutput, String> {
+        let id = self.alloc_id();
+        self.send(&Request::Run {
+            id,
+            argv: argv.to_vec(),
+        })?;
+        self.wait_for(id)
+    }
+
+    /// Writes the data to the file in the guest.
+    pub(super) fn put(&mut self, path: &str, data: &[u8], mode: u32) -> Result<(), String> {
+        // An empty file is created by a request with no data.
+        let chunks = data.chunks(PUT_CHUNK_SIZE);
+        let chunks = chunks.chain(data.is_empty().then_some(&[][..]));
+        for (index, chunk) in chunks.enumerate() {
+            let id = self.alloc_id();
+            self.send(&Request::Put {
+                id,
+                path: path.to_owned(),
+                mode,
+                offset: index * PUT_CHUNK_SIZE,
+                data: chunk.to_vec(),
+            })?;
+            let output = self.wait_for(id)?;
+            if output.exit_code != 0 {
+                return Err(format!(
+                    "cannot write {} in the guest: {}",
+                    path,
+                    String::from_utf8_lossy(&output.stderr).trim_end()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the file in the guest.
+    pub(super) fn get(&mut self, path: &str) -> Result<Vec<u8>, String> {
+        let id = self.alloc_id();
+        self.send(&Request::Get {
+            id,
+            path: path.to_owned(),
+        })?;
+        let output = self.wait_for(id)?;
+        if output.exit_code != 0 {
+            return Err(format!(
+                "cannot read {} in the guest: {}",
+                path,
+                String::from_utf8_lossy(&output.stderr).trim_end()
+            ));
+        }
+        Ok(output.stdout)
+    }
+
+    /// Asks the agent to exit with the exit code and waits for the kernel to
+    /// exit.
+    ///
+    /// Returns the console output of the guest.
+    pub(super) fn quit(mut self, code: i32) -> Result<String, String> {
+        self.send(&Request::Quit { code })?;
+        loop {
+            match self.recv_line() {
+                Ok(line) => self.console.push_str(&line),
+                Err(RecvTimeoutError::Disconnected) => return Ok(self.console),
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err("the kernel timed out after the guest agent exits".to_owned())
+                }
+            }
+        }
+    }
+
+    /// Returns the console output of the guest received so far.
+    pub(super) fn console(&self) -> &str {
+        &self.console
+    }
+
+    fn alloc_id(&mut self) -> u32 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    fn send(&mut self, request: &Request) -> Result<(), String> {
+        self.input
+            .write_all(request.encode().as_bytes())
+            .and_then(|()| self.input.flush())
+            .map_err(|err| format!("cannot send the request to the guest agent: {}", err))
+    }
+
+    /// Collects the responses of the request until it is finished.
+    fn wait_for(&mut self, id: u32) -> Result<CommandOutput, String> {
+        let mut output = CommandOutput::default();
+        loop {
+            match self.recv()? {
+                Response::Stdout { id: resp_id, data } if resp_id == id => {
+                    output.stdout.extend(data)
+                }
+                Response::Stderr { id: resp_id, data } if resp_id == id => {
+                    output.stderr.extend(data)
+                }
+                Response::Exit { id: resp_id, code } if resp_id == id => {
+                    output.exit_code = code;
+                    return Ok(output);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Receives the next response of the agent.
+    ///
+    /// The other lines of the console output are recorded.
+    fn recv(&mut self) -> Result<Response, String> {
+        loop {
+            let line = self.recv_line().map_err(|err| match err {
+                RecvTimeoutError::Timeout => "the guest agent timed out".to_owned(),
+                RecvTimeoutError::Disconnected => {
+                    "the kernel exited before the guest agent finished".to_owned()
+                }
+            })?;
+            match Response::decode(&line) {
+                Some(response) => return Ok(response),
+                None => self.console.push_str(&line),
+            }
+        }
+    }
+
+    fn recv_line(&self) -> Result<String, RecvTimeoutError> {
+        match self.deadline {
+            Some(deadline) => self
+                .lines
+                .recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => self
+                .lines
+                .recv()
+                .map_err(|_| RecvTimeoutError::Disconnected),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn session<'a>(input: &'a mut Vec<u8>, output: &str) -> AgentSession<&'a mut Vec<u8>> {
+        AgentSession::new(input, Cursor::new(output.to_owned()), None)
+    }
+
+    #[test]
+    fn encode_requests() {
+        let run = Request::Run {
+            id: 1,
+            argv: vec!["echo".to_owned(), "a b".to_owned()],
+        };
+        assert_eq!(run.encode(), "@osdk-agent RUN 1 6563686f 612062\n");
+        let put = Request::Put {
+            id: 2,
+            path: "/a".to_owned(),
+            mode: 0o755,
+            offset: 0,
+            data: vec![0, 0xff],
+        };
+        assert_eq!(put.encode(), "@osdk-agent PUT 2 2f61 755 0 00ff\n");
+        assert_eq!(Request::Quit { code: 0 }.encode(), "@osdk-agent QUIT 0\n");
+    }
+
+    #[test]
+    fn decode_responses() {
+        assert_eq!(
+            Response::decode("@osdk-agent READY\r\n"),
+            Some(Response::Ready)
+        );
+        assert_eq!(
+            Response::decode("[ 1.0] log@osdk-agent OUT 3 686921\n"),
+            Some(Response::Stdout {
+                id: 3,
+                data: b"hi!".to_vec()
+            })
+        );
+        assert_eq!(
+            Response::decode("@osdk-agent EXIT 3 -1"),
+            Some(Response::Exit { id: 3, code: -1 })
+        );
+        assert_eq!(Response::decode("hello"), None);
+        assert_eq!(Response::decode("@osdk-agent OUT 3 6"), None);
+        assert_eq!(Response::decode("@osdk-agent EXIT 3 0 0"), None);
+        // The requests echoed by the terminal are not responses.
+        assert_eq!(Response::decode("@osdk-agent QUIT 0"), None);
+    }
+
+    #[test]
+    fn run_command() {
+        let mut input = Vec::new();
+        let mut session = session(
+            &mut input,
+            "boot\n@osdk-agent READY\n@osdk-agent OUT 1 6869\n\
+             log\n@osdk-agent ERR 1 21\n@osdk-agent EXIT 1 2\n",
+        );
+        session.wait_ready().unwrap();
+        let output = session.run(&["true".to_owned()]).unwrap();
+        assert_eq!(
+            output,
+            CommandOutput {
+                stdout: b"hi".to_vec(),
+                stderr: b"!".to_vec(),
+                exit_code: 2,
+            }
+        );
+        assert_eq!(session.console(), "boot\nlog\n");
+        assert!(session.run(&["true".to_owned()]).is_err());
+        drop(session);
+        assert_eq!(
+            String::from_utf8(input).unwrap(),
+            "@osdk-agent RUN 1 74727565\n@osdk-agent RUN 2 74727565\n"
+        );
+    }
+
+    #[test]
+    fn put_file_in_chunks() {
+        let mut input = Vec::new();
+        let mut session = session(
+            &mut input,
+            "@osdk-agent READY\n@osdk-agent EXIT 1 0\n@osdk-agent EXIT 2 0\n",
+        );
+        session.wait_ready().unwrap();
+        session
+            .put("/f", &vec![0; PUT_CHUNK_SIZE + 1], 0o644)
+            .unwrap();
+        drop(session);
+        let input = String::from_utf8(input).unwrap();
+        let offsets: Vec<_> = input
+            .lines()
+            .map(|line| line.split(' ').nth(5).unwrap())
+            .collect();
+        assert_eq!(offsets, ["0", "2048"]);
+    }
+}
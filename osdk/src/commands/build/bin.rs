@@ -17,6 +17,7 @@ use crate::{
         bin::{AsterBin, AsterBinType, AsterBzImageMeta, AsterElfMeta},
         file::BundleFile,
     },
+    diagnostic::exit_on_launch_failure,
     util::{get_current_crates, hard_link_or_copy},
 };
 
@@ -99,21 +100,16 @@ pub fn make_elf_for_qemu(install_dir: impl AsRef<Path>, elf: &AsterBin, strip: b
             .arg(elf.path())
             .arg("-o")
             .arg(result_elf_path.as_os_str())
-            .status();
-
-        match status {
-            Ok(status) => {
-                if !status.success() {
-                    panic!("Failed to strip kernel elf.");
-                }
-            }
-            Err(err) => match err.kind() {
-                std::io::ErrorKind::NotFound => panic!(
-                    "`rust-strip` command not found. Please
-                    try `cargo install cargo-binutils` and then rerun."
-                ),
-                _ => panic!("Strip kernel elf failed, err:{:#?}", err),
-            },
+            .status()
+            .unwrap_or_else(|err| {
+                exit_on_launch_failure(
+                    &"rust-strip",
+                    err,
+                    "install it with `cargo install cargo-binutils`",
+                )
+            });
+        if !status.success() {
+            panic!("Failed to strip kernel elf.");
         }
     } else {
         // Copy the ELF file.
@@ -0,0 +1,602 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Assembling a GPT disk image that boots the kernel with GRUB on UEFI.
+//!
+//! The disk image is laid out as follows, where each partition is aligned to 1 MiB:
+//! ```text
+//! +-----+-------------+-----------------------+-------------+-----+-------------+
+//! | MBR | primary GPT | EFI system partition  | partition 2 | ... | backup GPT  |
+//! +-----+-------------+-----------------------+-------------+-----+-------------+
+//! ```
+//! The EFI system partition (ESP) contains GRUB as `/EFI/BOOT/BOOTX64.EFI`, its configuration,
+//! the kernel and the initramfs. The other partitions are given in the `disk_image` section of
+//! the manifest.
+//!
+//! The image is reproducible, i.e., the same inputs result in the same image. The GUIDs are
+//! derived from the partition labels, and the FAT file system is created with a fixed serial
+//! number and fixed timestamps.
+
+use std::{
+    fs::{self, File},
+    io::{Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, SystemTime},
+};
+
+use super::{bin::make_install_bzimage, grub::generate_grub_cfg};
+use crate::{
+    bundle::{bin::AsterBinType, file::BundleFile, Bundle},
+    config::{
+        scheme::{ActionChoice, BootProtocol, DiskImageFormat},
+        Config,
+    },
+    diagnostic::exit_on_launch_failure,
+    error::Errno,
+    exit_with_error,
+    util::get_current_crates,
+};
+
+const SECTOR_SIZE: u64 = 512;
+const PARTITION_ALIGNMENT: u64 = 1024 * 1024;
+const MIN_ESP_SIZE: u64 = 64 * 1024 * 1024;
+
+const GPT_NUM_ENTRIES: u64 = 128;
+const GPT_ENTRY_SIZE: u64 = 128;
+const GPT_ENTRIES_SECTORS: u64 = GPT_NUM_ENTRIES * GPT_ENTRY_SIZE / SECTOR_SIZE;
+const GPT_HEADER_SIZE: usize = 92;
+/// The maximum number of UTF-16 code units in a partition name.
+const GPT_NAME_LEN: usize = 36;
+
+const ESP_TYPE_GUID: Guid = guid(
+    0xc12a7328,
+    0xf81f,
+    0x11d2,
+    [0xba, 0x4b, 0x00, 0xa0, 0xc9, 0x3e, 0xc9, 0x3b],
+);
+const LINUX_DATA_TYPE_GUID: Guid = guid(
+    0x0fc63daf,
+    0x8483,
+    0x4772,
+    [0x8e, 0x79, 0x3d, 0x69, 0xd8, 0x47, 0x7d, 0xe4],
+);
+
+/// The timestamp of the files in the ESP if `SOURCE_DATE_EPOCH` is not set, i.e.,
+/// 1980-01-01 00:00:00 UTC, the earliest time that FAT supports.
+const DEFAULT_SOURCE_DATE_EPOCH: u64 = 315532800;
+
+/// The GRUB modules built into `BOOTX64.EFI`.
+const GRUB_MODULES: &[&str] = &[
+    "normal",
+    "boot",
+    "part_gpt",
+    "fat",
+    "all_video",
+    "gzio",
+    "multiboot",
+    "multiboot2",
+    "linux",
+];
+
+pub fn create_disk_image(
+    target_dir: impl AsRef<Path>,
+    bundle: &Bundle,
+    config: &Config,
+    action: ActionChoice,
+    format: DiskImageFormat,
+) -> PathBuf {
+    let target_name = get_current_crates().remove(0).name;
+    let target_dir = target_dir.as_ref();
+    let esp_root = &target_dir.join("esp_root");
+    let action = match action {
+        ActionChoice::Run => &config.run,
+        ActionChoice::Test => &config.test,
+    };
+    let protocol = &action.grub.boot_protocol;
+
+    if esp_root.exists() {
+        fs::remove_dir_all(esp_root).unwrap();
+    }
+    let boot_dir = esp_root.join("boot");
+    fs::create_dir_all(boot_dir.join("grub")).unwrap();
+    fs::create_dir_all(esp_root.join("EFI").join("BOOT")).unwrap();
+
+    // Place the kernel and the initramfs in the boot directory as in the ISO image. The files
+    // are copied instead of hard-linked since their timestamps are changed later.
+    let aster_bin = bundle.aster_bin().unwrap();
+    match protocol {
+        BootProtocol::Linux if !matches!(aster_bin.typ(), AsterBinType::BzImage(_)) => {
+            make_install_bzimage(
+                &boot_dir,
+                target_dir,
+                &aster_bin,
+                action.build.linux_x86_legacy_boot,
+                config.build.encoding.clone(),
+            );
+        }
+        _ => {
+            fs::copy(aster_bin.path(), boot_dir.join(&target_name)).unwrap();
+        }
+    }
+    let initramfs_path = bundle.initramfs_path();
+    if let Some(path) = &initramfs_path {
+        fs::copy(path, boot_dir.join("initramfs.cpio.gz")).unwrap();
+    }
+    let grub_cfg = generate_grub_cfg(
+        &action.boot.kcmdline.join(" "),
+        !action.grub.display_grub_menu,
+        initramfs_path.map(|_| "/boot/initramfs.cpio.gz".to_string()),
+        protocol,
+    );
+    fs::write(boot_dir.join("grub").join("grub.cfg"), grub_cfg).unwrap();
+
+    // The configuration is found in the `/boot/grub` directory of the ESP, from which GRUB
+    // is loaded.
+    let grub_mkimage = action.grub.grub_mkrescue.with_file_name("grub-mkimage");
+    let mut grub_mkimage_cmd = Command::new(&grub_mkimage);
+    grub_mkimage_cmd
+        .args(["-O", "x86_64-efi", "-p", "/boot/grub", "-o"])
+        .arg(esp_root.join("EFI").join("BOOT").join("BOOTX64.EFI"))
+        .args(GRUB_MODULES);
+    run_tool(
+        &mut grub_mkimage_cmd,
+        "install GRUB with the `x86_64-efi` platform, \
+         or set `grub.grub_mkrescue` in the manifest to a GRUB installation with it",
+    );
+
+    // Make the ESP.
+    let source_date_epoch = source_date_epoch();
+    let esp_size = config
+        .disk_image
+        .esp_size
+        .unwrap_or_else(|| default_esp_size(esp_root));
+    let esp_image = &target_dir.join("esp.img");
+    make_fat_image(esp_image, esp_root, esp_size, source_date_epoch);
+
+    // Lay out the partitions.
+    let mut sources = vec![Some(esp_image.clone())];
+    let mut partitions = vec![PartitionSpec {
+        label: "EFI system partition".to_string(),
+        type_guid: ESP_TYPE_GUID,
+        size: esp_size,
+    }];
+    for partition in config.disk_image.partitions.iter() {
+        let image_size = partition.image.as_ref().map(|image| {
+            fs::metadata(image)
+                .unwrap_or_else(|err| {
+                    exit_with_error!(
+                        Errno::GetMetadata,
+                        "Cannot read the image `{}` of the partition `{}`: {}",
+                        image.display(),
+                        partition.label,
+                        err
+                    )
+                })
+                .len()
+        });
+        let size = match (partition.size, image_size) {
+            (Some(size), Some(image_size)) if size < image_size => exit_with_error!(
+                Errno::ParseMetadata,
+                "The partition `{}` is smaller than its image",
+                partition.label
+            ),
+            (Some(size), _) => size,
+            (None, Some(image_size)) => image_size,
+            (None, None) => exit_with_error!(
+                Errno::ParseMetadata,
+                "The partition `{}` needs either a size or an image",
+                partition.label
+            ),
+        };
+        sources.push(partition.image.clone());
+        partitions.push(PartitionSpec {
+            label: partition.label.clone(),
+            type_guid: LINUX_DATA_TYPE_GUID,
+            size,
+        });
+    }
+    let layout = DiskLayout::new(&partitions, config.disk_image.size).unwrap_or_else(|msg| {
+        exit_with_error!(Errno::ParseMetadata, "Invalid disk image layout: {}", msg)
+    });
+
+    // Write the raw disk image.
+    let raw_path = target_dir.join(format!("{}-disk.img", target_name));
+    let mut disk = File::create(&raw_path).unwrap();
+    disk.set_len(layout.disk_sectors * SECTOR_SIZE).unwrap();
+    let (primary_gpt, backup_gpt) = layout.gpt(&target_name);
+    disk.write_all(&primary_gpt).unwrap();
+    disk.seek(SeekFrom::Start(layout.backup_gpt_lba() * SECTOR_SIZE))
+        .unwrap();
+    disk.write_all(&backup_gpt).unwrap();
+    for (source, extent) in sources.iter().zip(layout.partitions.iter()) {
+        if let Some(source) = source {
+            disk.seek(SeekFrom::Start(extent.first_lba * SECTOR_SIZE))
+                .unwrap();
+            std::io::copy(&mut File::open(source).unwrap(), &mut disk).unwrap();
+        }
+    }
+    drop(disk);
+    fs::remove_file(esp_image).unwrap();
+
+    match format {
+        DiskImageFormat::Raw => raw_path,
+        DiskImageFormat::Qcow2 => {
+            let qcow2_path = raw_path.with_extension("qcow2");
+            let mut qemu_img = Command::new("qemu-img");
+            qemu_img
+                .args(["convert", "-f", "raw", "-O", "qcow2"])
+                .arg(&raw_path)
+                .arg(&qcow2_path);
+            run_tool(
+                &mut qemu_img,
+                "install `qemu-img`, which is shipped with QEMU",
+            );
+            fs::remove_file(&raw_path).unwrap();
+            qcow2_path
+        }
+    }
+}
+
+/// Returns the size of the ESP that fits the files in the directory with some slack for the
+/// file system metadata.
+fn default_esp_size(dir: &Path) -> u64 {
+    fn dir_size(dir: &Path) -> u64 {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let metadata = entry.metadata().unwrap();
+                if metadata.is_dir() {
+                    dir_size(&entry.path())
+                } else {
+                    metadata.len()
+                }
+            })
+            .sum()
+    }
+
+    let size = dir_size(dir);
+    align_up(size + size / 10 + 4 * 1024 * 1024, PARTITION_ALIGNMENT).max(MIN_ESP_SIZE)
+}
+
+/// Makes a FAT file system image with the files in the directory using `mkfs.fat` and `mcopy`.
+fn make_fat_image(image: &Path, dir: &Path, size: u64, source_date_epoch: u64) {
+    if image.exists() {
+        fs::remove_file(image).unwrap();
+    }
+
+    // Both `mkfs.fat --invariant` and `mcopy -m` take the timestamps from the files, so the
+    // timestamps are fixed before the files are copied.
+    let timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(source_date_epoch);
+    set_modified_time(dir, timestamp);
+
+    let mut mkfs_fat = Command::new("mkfs.fat");
+    mkfs_fat
+        .args(["-C", "--invariant", "-i", "0a57e71a", "-n", "ESP"])
+        .arg(image)
+        .arg((size / 1024).to_string());
+    run_tool(&mut mkfs_fat, "install `dosfstools`");
+
+    let mut mcopy = Command::new("mcopy");
+    mcopy
+        .env("SOURCE_DATE_EPOCH", source_date_epoch.to_string())
+        .arg("-i")
+        .arg(image)
+        .args(["-s", "-m", "-Q"]);
+    for entry in fs::read_dir(dir).unwrap() {
+        mcopy.arg(entry.unwrap().path());
+    }
+    mcopy.arg("::/");
+    run_tool(&mut mcopy, "install `mtools`");
+}
+
+fn set_modified_time(path: &Path, timestamp: SystemTime) {
+    if path.is_dir() {
+        for entry in fs::read_dir(path).unwrap() {
+            set_modified_time(&entry.unwrap().path(), timestamp);
+        }
+    }
+    File::open(path).unwrap().set_modified(timestamp).unwrap();
+}
+
+/// Returns the timestamp in seconds for reproducible builds.
+///
+/// See <https://reproducible-builds.org/specs/source-date-epoch/>.
+fn source_date_epoch() -> u64 {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .map_or(DEFAULT_SOURCE_DATE_EPOCH, |epoch| {
+            epoch.max(DEFAULT_SOURCE_DATE_EPOCH)
+        })
+}
+
+fn run_tool(cmd: &mut Command, help: &str) {
+    info!("Making the disk image using {:#?}", cmd);
+    let status = cmd.status().unwrap_or_else(|err| {
+        exit_on_launch_failure(&cmd.get_program().to_string_lossy(), err, help)
+    });
+    if !status.success() {
+        exit_with_error!(Errno::ExecuteCommand, "Failed to run {:#?}", cmd);
+    }
+}
+
+/// A GUID in the mixed-endian format of GPT.
+type Guid = [u8; 16];
+
+const fn guid(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> Guid {
+    let data1 = data1.to_le_bytes();
+    let data2 = data2.to_le_bytes();
+    let data3 = data3.to_le_bytes();
+    [
+        data1[0], data1[1], data1[2], data1[3], data2[0], data2[1], data3[0], data3[1], data4[0],
+        data4[1], data4[2], data4[3], data4[4], data4[5], data4[6], data4[7],
+    ]
+}
+
+/// Derives a random-looking but stable version 4 GUID from the name.
+fn derive_guid(name: &str) -> Guid {
+    let mut guid = [0; 16];
+    for (i, chunk) in guid.chunks_mut(4).enumerate() {
+        chunk.copy_from_slice(&crc32(format!("{}:{}", i, name).as_bytes()).to_le_bytes());
+    }
+    guid[7] = (guid[7] & 0x0f) | 0x40;
+    guid[8] = (guid[8] & 0x3f) | 0x80;
+    guid
+}
+
+/// Computes the CRC32 checksum used by GPT, i.e., the one of zlib.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn align_up(value: u64, align: u64) -> u64 {
+    value.div_ceil(align) * align
+}
+
+struct PartitionSpec {
+    label: String,
+    type_guid: Guid,
+    /// The size in bytes.
+    size: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct PartitionExtent {
+    label: String,
+    type_guid: Guid,
+    first_lba: u64,
+    last_lba: u64,
+}
+
+#[derive(Debug)]
+struct DiskLayout {
+    disk_sectors: u64,
+    partitions: Vec<PartitionExtent>,
+}
+
+impl DiskLayout {
+    /// Places the partitions one after another, and checks that they fit in the disk if the
+    /// disk size is given.
+    fn new(partitions: &[PartitionSpec], disk_size: Option<u64>) -> Result<Self, String> {
+        if partitions.len() as u64 > GPT_NUM_ENTRIES {
+            return Err(format!(
+                "at most {} partitions are allowed",
+                GPT_NUM_ENTRIES
+            ));
+        }
+
+        let mut offset = PARTITION_ALIGNMENT;
+        let mut extents = Vec::new();
+        for partition in partitions {
+            if partition.label.encode_utf16().count() > GPT_NAME_LEN {
+                return Err(format!(
+                    "the label `{}` is longer than {} characters",
+                    partition.label, GPT_NAME_LEN
+                ));
+            }
+            if partition.size == 0 {
+                return Err(format!("the partition `{}` is empty", partition.label));
+            }
+            let sectors = partition.size.div_ceil(SECTOR_SIZE);
+            extents.push(PartitionExtent {
+                label: partition.label.clone(),
+                type_guid: partition.type_guid,
+                first_lba: offset / SECTOR_SIZE,
+                last_lba: offset / SECTOR_SIZE + sectors - 1,
+            });
+            offset = align_up(offset + sectors * SECTOR_SIZE, PARTITION_ALIGNMENT);
+        }
+
+        // Leave an aligned room for the backup GPT.
+        let min_disk_size = offset + PARTITION_ALIGNMENT;
+        let disk_size = match disk_size {
+            Some(size) if size < min_disk_size => {
+                return Err(format!(
+                    "the disk size {} is smaller than the {} bytes needed by the partitions",
+                    size, min_disk_size
+                ));
+            }
+            Some(size) => size,
+            None => min_disk_size,
+        };
+
+        Ok(Self {
+            disk_sectors: disk_size.div_ceil(SECTOR_SIZE),
+            partitions: extents,
+        })
+    }
+
+    fn backup_gpt_lba(&self) -> u64 {
+        self.disk_sectors - 1 - GPT_ENTRIES_SECTORS
+    }
+
+    /// Returns the protective MBR with the primary GPT, which is placed at the start of the
+    /// disk, and the backup GPT, which is placed at [`Self::backup_gpt_lba`].
+    ///
+    /// The GUIDs are derived from the disk name and the partition labels.
+    fn gpt(&self, disk_name: &str) -> (Vec<u8>, Vec<u8>) {
+        let mut entries = vec![0u8; (GPT_NUM_ENTRIES * GPT_ENTRY_SIZE) as usize];
+        for (partition, entry) in self
+            .partitions
+            .iter()
+            .zip(entries.chunks_mut(GPT_ENTRY_SIZE as usize))
+        {
+            entry[0..16].copy_from_slice(&partition.type_guid);
+            entry[16..32]
+                .copy_from_slice(&derive_guid(&format!("{}/{}", disk_name, partition.label)));
+            entry[32..40].copy_from_slice(&partition.first_lba.to_le_bytes());
+            entry[40..48].copy_from_slice(&partition.last_lba.to_le_bytes());
+            for (i, unit) in partition.label.encode_utf16().enumerate() {
+                entry[56 + i * 2..58 + i * 2].copy_from_slice(&unit.to_le_bytes());
+            }
+        }
+        let entries_crc = crc32(&entries);
+
+        let last_lba = self.disk_sectors - 1;
+        let disk_guid = derive_guid(disk_name);
+        let header = |current_lba: u64, backup_lba: u64, entries_lba: u64| {
+            let mut header = vec![0u8; SECTOR_SIZE as usize];
+            header[0..8].copy_from_slice(b"EFI PART");
+            header[8..12].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+            header[12..16].copy_from_slice(&(GPT_HEADER_SIZE as u32).to_le_bytes());
+            header[24..32].copy_from_slice(&current_lba.to_le_bytes());
+            header[32..40].copy_from_slice(&backup_lba.to_le_bytes());
+            header[40..48].copy_from_slice(&(2 + GPT_ENTRIES_SECTORS).to_le_bytes());
+            header[48..56].copy_from_slice(&(last_lba - 1 - GPT_ENTRIES_SECTORS).to_le_bytes());
+            header[56..72].copy_from_slice(&disk_guid);
+            header[72..80].copy_from_slice(&entries_lba.to_le_bytes());
+            header[80..84].copy_from_slice(&(GPT_NUM_ENTRIES as u32).to_le_bytes());
+            header[84..88].copy_from_slice(&(GPT_ENTRY_SIZE as u32).to_le_bytes());
+            header[88..92].copy_from_slice(&entries_crc.to_le_bytes());
+            let header_crc = crc32(&header[..GPT_HEADER_SIZE]);
+            header[16..20].copy_from_slice(&header_crc.to_le_bytes());
+            header
+        };
+
+        // The protective MBR has a single partition that covers the whole disk.
+        let mut primary = vec![0u8; SECTOR_SIZE as usize];
+        primary[446..454].copy_from_slice(&[0x00, 0x00, 0x02, 0x00, 0xee, 0xff, 0xff, 0xff]);
+        primary[454..458].copy_from_slice(&1u32.to_le_bytes());
+        let mbr_sectors = last_lba.min(u32::MAX as u64) as u32;
+        primary[458..462].copy_from_slice(&mbr_sectors.to_le_bytes());
+        primary[510..512].copy_from_slice(&[0x55, 0xaa]);
+        primary.extend(header(1, last_lba, 2));
+        primary.extend(&entries);
+
+        let backup_entries_lba = self.backup_gpt_lba();
+        let mut backup = entries;
+        backup.extend(header(last_lba, 1, backup_entries_lba));
+
+        (primary, backup)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn spec(label: &str, size: u64) -> PartitionSpec {
+        PartitionSpec {
+            label: label.to_string(),
+            type_guid: LINUX_DATA_TYPE_GUID,
+            size,
+        }
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn known_guids() {
+        // C12A7328-F81F-11D2-BA4B-00A0C93EC93B
+        assert_eq!(
+            ESP_TYPE_GUID,
+            [
+                0x28, 0x73, 0x2a, 0xc1, 0x1f, 0xf8, 0xd2, 0x11, 0xba, 0x4b, 0x00, 0xa0, 0xc9, 0x3e,
+                0xc9, 0x3b
+            ]
+        );
+        assert_eq!(derive_guid("asterinas"), derive_guid("asterinas"));
+        assert_ne!(derive_guid("asterinas/a"), derive_guid("asterinas/b"));
+        assert_eq!(derive_guid("asterinas")[7] >> 4, 4);
+    }
+
+    #[test]
+    fn lay_out_partitions() {
+        let layout = DiskLayout::new(&[spec("esp", 64 * MIB), spec("data", 1000)], None).unwrap();
+        assert_eq!(
+            layout
+                .partitions
+                .iter()
+                .map(|extent| (extent.first_lba, extent.last_lba))
+                .collect::<Vec<_>>(),
+            vec![(2048, 2048 + 131072 - 1), (133120, 133120 + 1)]
+        );
+        // The backup GPT has its own aligned room after the last partition.
+        assert_eq!(layout.disk_sectors, 135168 + 2048);
+
+        let layout = DiskLayout::new(&[spec("esp", 64 * MIB)], Some(1024 * MIB)).unwrap();
+        assert_eq!(layout.disk_sectors, 2 * 1024 * 1024);
+
+        assert!(DiskLayout::new(&[spec("esp", 64 * MIB)], Some(64 * MIB)).is_err());
+        assert!(DiskLayout::new(&[spec("esp", 0)], None).is_err());
+        assert!(DiskLayout::new(&[spec(&"x".repeat(37), MIB)], None).is_err());
+    }
+
+    #[test]
+    fn write_gpt() {
+        let layout = DiskLayout::new(&[spec("esp", MIB)], None).unwrap();
+        let (primary, backup) = layout.gpt("asterinas");
+        let sector = SECTOR_SIZE as usize;
+        assert_eq!(primary.len(), (2 + GPT_ENTRIES_SECTORS as usize) * sector);
+        assert_eq!(backup.len(), (1 + GPT_ENTRIES_SECTORS as usize) * sector);
+        assert_eq!(primary[450], 0xee);
+        assert_eq!(&primary[510..512], &[0x55, 0xaa]);
+
+        let primary_header = &primary[sector..2 * sector];
+        let backup_header = &backup[backup.len() - sector..];
+        for header in [primary_header, backup_header] {
+            assert_eq!(&header[0..8], b"EFI PART");
+            let mut zeroed = header[..GPT_HEADER_SIZE].to_vec();
+            zeroed[16..20].fill(0);
+            assert_eq!(crc32(&zeroed).to_le_bytes(), header[16..20]);
+        }
+        // The current and backup LBAs are swapped in the backup header.
+        let last_lba = layout.disk_sectors - 1;
+        assert_eq!(
+            primary_header[24..40],
+            [1u64.to_le_bytes(), last_lba.to_le_bytes()].concat()
+        );
+        assert_eq!(
+            backup_header[24..40],
+            [last_lba.to_le_bytes(), 1u64.to_le_bytes()].concat()
+        );
+        assert_eq!(backup_header[72..80], layout.backup_gpt_lba().to_le_bytes());
+
+        // The entries are the same in both copies.
+        assert_eq!(&primary[2 * sector..], &backup[..backup.len() - sector]);
+        let entry = &primary[2 * sector..2 * sector + GPT_ENTRY_SIZE as usize];
+        assert_eq!(entry[0..16], LINUX_DATA_TYPE_GUID);
+        assert_eq!(entry[32..40], 2048u64.to_le_bytes());
+        assert_eq!(entry[56..62], [b'e', 0, b's', 0, b'p', 0]);
+
+        // The image is reproducible.
+        assert_eq!(layout.gpt("asterinas"), (primary, backup));
+    }
+}
@@ -16,6 +16,7 @@ use crate::{
         scheme::{ActionChoice, BootProtocol},
         Config,
     },
+    diagnostic::exit_on_launch_failure,
     util::{get_current_crates, hard_link_or_copy},
 };
 
@@ -89,7 +90,14 @@ pub fn create_bootdev_image(
         .arg(iso_root.as_os_str())
         .arg("-o")
         .arg(iso_path);
-    if !grub_mkrescue_cmd.status().unwrap().success() {
+    let status = grub_mkrescue_cmd.status().unwrap_or_else(|err| {
+        exit_on_launch_failure(
+            &action.grub.grub_mkrescue.display(),
+            err,
+            "install GRUB and `xorriso`, or set `grub.grub_mkrescue` in the manifest",
+        )
+    });
+    if !status.success() {
         panic!("Failed to run {:#?}.", grub_mkrescue_cmd);
     }
 
@@ -102,7 +110,7 @@ pub fn create_bootdev_image(
     )
 }
 
-fn generate_grub_cfg(
+pub(super) fn generate_grub_cfg(
     kcmdline: &str,
     skip_grub_menu: bool,
     initramfs_path: Option<String>,
@@ -128,40 +136,26 @@ fn generate_grub_cfg(
         .into_os_string()
         .into_string()
         .unwrap();
+    let (kernel_cmd, initramfs_cmd) = grub_boot_commands(protocol);
+    grub_cfg
+        .replace("#GRUB_CMD_KERNEL#", kernel_cmd)
+        .replace("#KERNEL#", &aster_bin_path_on_device)
+        .replace(
+            "#GRUB_CMD_INITRAMFS#",
+            &if let Some(p) = &initramfs_path {
+                initramfs_cmd.to_owned() + " " + p
+            } else {
+                "".to_owned()
+            },
+        )
+}
+
+/// Returns the GRUB commands to load the kernel and the initramfs with the boot protocol.
+pub fn grub_boot_commands(protocol: &BootProtocol) -> (&'static str, &'static str) {
     match protocol {
-        BootProtocol::Multiboot => grub_cfg
-            .replace("#GRUB_CMD_KERNEL#", "multiboot")
-            .replace("#KERNEL#", &aster_bin_path_on_device)
-            .replace(
-                "#GRUB_CMD_INITRAMFS#",
-                &if let Some(p) = &initramfs_path {
-                    "module --nounzip ".to_owned() + p
-                } else {
-                    "".to_owned()
-                },
-            ),
-        BootProtocol::Multiboot2 => grub_cfg
-            .replace("#GRUB_CMD_KERNEL#", "multiboot2")
-            .replace("#KERNEL#", &aster_bin_path_on_device)
-            .replace(
-                "#GRUB_CMD_INITRAMFS#",
-                &if let Some(p) = &initramfs_path {
-                    "module2 --nounzip ".to_owned() + p
-                } else {
-                    "".to_owned()
-                },
-            ),
-        BootProtocol::Linux => grub_cfg
-            .replace("#GRUB_CMD_KERNEL#", "linux")
-            .replace("#KERNEL#", &aster_bin_path_on_device)
-            .replace(
-                "#GRUB_CMD_INITRAMFS#",
-                &if let Some(p) = &initramfs_path {
-                    "initrd ".to_owned() + p
-                } else {
-                    "".to_owned()
-                },
-            ),
+        BootProtocol::Multiboot => ("multiboot", "module --nounzip"),
+        BootProtocol::Multiboot2 => ("multiboot2", "module2 --nounzip"),
+        BootProtocol::Linux => ("linux", "initrd"),
     }
 }
 
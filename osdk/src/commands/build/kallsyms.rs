@@ -0,0 +1,259 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Embedding the symbol table into the kernel ELF.
+//!
+//! The table is written into the `.kallsyms` section reserved by OSTD. See
+//! `ostd/src/kallsyms.rs` for the format of the table.
+
+use std::{fs, path::Path, process::Command};
+
+use crate::{error::Errno, exit_with_error, warn_msg};
+
+const SECTION_NAME: &str = ".kallsyms";
+const MAGIC: &[u8; 4] = b"KSYM";
+const SYMBOLS_PER_MARKER: usize = 16;
+const SYMBOL_NAME_MAX: usize = u8::MAX as usize;
+
+/// A symbol listed by `nm`.
+#[derive(Debug, PartialEq, Eq)]
+struct Symbol {
+    addr: u64,
+    size: u32,
+    type_: u8,
+    name: String,
+}
+
+/// Embeds the symbol table into the kernel ELF in place.
+///
+/// The ELF is left untouched if the table has already been embedded, so
+/// embedding the table does not invalidate the cached bundles. If the symbols
+/// cannot be listed, the kernel is left without symbols. If the table does not
+/// fit in the reserved section, the build fails instead of embedding a partial
+/// table.
+pub fn embed_kallsyms(elf_path: &Path) {
+    let mut elf = fs::read(elf_path).unwrap();
+    let Some(section) = find_section(&elf, SECTION_NAME) else {
+        // The kernel is built against an OSTD without the symbol table.
+        return;
+    };
+    if elf[section.clone()].starts_with(MAGIC) {
+        return;
+    }
+
+    let Some(symbols) = list_symbols(elf_path) else {
+        return;
+    };
+    let table = encode_table(&symbols);
+    if table.len() > section.len() {
+        exit_with_error!(
+            Errno::BuildCrate,
+            "The kernel symbol table ({} bytes) does not fit in the reserved space ({} bytes), \
+             please enlarge `KALLSYMS_CAPACITY` in `ostd/src/kallsyms.rs`",
+            table.len(),
+            section.len()
+        );
+    }
+
+    elf[section.start..section.start + table.len()].copy_from_slice(&table);
+    fs::write(elf_path, elf).unwrap();
+    info!("Embedded {} kernel symbols", symbols.len());
+}
+
+/// Lists the symbols defined in the ELF with `nm`, sorted by their addresses.
+fn list_symbols(elf_path: &Path) -> Option<Vec<Symbol>> {
+    let output = Command::new("nm")
+        .args([
+            "--defined-only",
+            "--numeric-sort",
+            "--print-size",
+            "--demangle",
+        ])
+        .arg(elf_path)
+        .output();
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            warn_msg!(
+                "Failed to list the kernel symbols with `nm`: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+            return None;
+        }
+        Err(err) => {
+            warn_msg!(
+                "Failed to execute `nm`, the kernel symbols are not embedded: {}",
+                err
+            );
+            return None;
+        }
+    };
+
+    let symbols = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(parse_nm_line)
+        .collect();
+    Some(symbols)
+}
+
+/// Parses a line of `nm`, which is `ADDR [SIZE] TYPE NAME`, where the name
+/// may contain spaces after being demangled.
+fn parse_nm_line(line: &str) -> Option<Symbol> {
+    let (addr, rest) = line.split_once(' ')?;
+    let addr = u64::from_str_radix(addr, 16).ok()?;
+
+    let (size, rest) = match rest.split_once(' ') {
+        Some((size, rest)) if size.len() > 1 => (u64::from_str_radix(size, 16).ok()?, rest),
+        _ => (0, rest),
+    };
+    let (type_, name) = rest.split_once(' ')?;
+    let &[type_] = type_.as_bytes() else {
+        return None;
+    };
+    // Only the symbols in the kernel image are kept, while the absolute
+    // symbols and the debugging symbols are not.
+    if !b"TtRrDdBbVvWw".contains(&type_) || name.is_empty() {
+        return None;
+    }
+
+    Some(Symbol {
+        addr,
+        size: size.try_into().unwrap_or(u32::MAX),
+        type_,
+        name: name.to_owned(),
+    })
+}
+
+/// Encodes the symbols, which are sorted by their addresses, into the table.
+fn encode_table(symbols: &[Symbol]) -> Vec<u8> {
+    let mut names = Vec::new();
+    let mut markers = Vec::new();
+    let mut last_name: &[u8] = &[];
+    for (i, symbol) in symbols.iter().enumerate() {
+        if i % SYMBOLS_PER_MARKER == 0 {
+            markers.push(names.len() as u32);
+            last_name = &[];
+        }
+        let name = truncate_name(&symbol.name);
+        let prefix_len = name
+            .iter()
+            .zip(last_name)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let suffix = &name[prefix_len..];
+        names.extend([symbol.type_, prefix_len as u8, suffix.len() as u8]);
+        names.extend_from_slice(suffix);
+        last_name = name;
+    }
+
+    let mut table = Vec::new();
+    table.extend_from_slice(MAGIC);
+    table.extend((symbols.len() as u32).to_le_bytes());
+    table.extend((names.len() as u32).to_le_bytes());
+    table.extend(0u32.to_le_bytes());
+    for symbol in symbols {
+        table.extend(symbol.addr.to_le_bytes());
+    }
+    for symbol in symbols {
+        table.extend(symbol.size.to_le_bytes());
+    }
+    for marker in markers {
+        table.extend(marker.to_le_bytes());
+    }
+    table.extend(names);
+    table
+}
+
+fn truncate_name(name: &str) -> &[u8] {
+    &name.as_bytes()[..name.len().min(SYMBOL_NAME_MAX)]
+}
+
+/// Returns the file range of the section in a little-endian ELF64 file.
+fn find_section(elf: &[u8], name: &str) -> Option<std::ops::Range<usize>> {
+    let read_u16 = |offset: usize| -> Option<usize> {
+        Some(u16::from_le_bytes(elf.get(offset..offset + 2)?.try_into().unwrap()) as usize)
+    };
+    let read_u32 = |offset: usize| -> Option<usize> {
+        Some(u32::from_le_bytes(elf.get(offset..offset + 4)?.try_into().unwrap()) as usize)
+    };
+    let read_u64 = |offset: usize| -> Option<usize> {
+        Some(u64::from_le_bytes(elf.get(offset..offset + 8)?.try_into().unwrap()) as usize)
+    };
+
+    // ELFCLASS64 and ELFDATA2LSB
+    if !elf.starts_with(b"\x7fELF\x02\x01") {
+        return None;
+    }
+    let shoff = read_u64(0x28)?;
+    let shentsize = read_u16(0x3a)?;
+    let shnum = read_u16(0x3c)?;
+    let shstrndx = read_u16(0x3e)?;
+    let section_header = |index: usize| shoff + index * shentsize;
+
+    let shstrtab = read_u64(section_header(shstrndx) + 0x18)?;
+    (0..shnum).find_map(|index| {
+        let header = section_header(index);
+        let name_offset = shstrtab + read_u32(header)?;
+        let section_name = elf.get(name_offset..)?.split(|b| *b == 0).next()?;
+        if section_name != name.as_bytes() {
+            return None;
+        }
+        const SHT_PROGBITS: usize = 1;
+        if read_u32(header + 0x4)? != SHT_PROGBITS {
+            return None;
+        }
+        let offset = read_u64(header + 0x18)?;
+        let size = read_u64(header + 0x20)?;
+        (offset + size <= elf.len()).then_some(offset..offset + size)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_nm_lines() {
+        assert_eq!(
+            parse_nm_line(
+                "ffffffff80200000 0000000000000042 T <ostd::Foo as core::ops::Drop>::drop"
+            ),
+            Some(Symbol {
+                addr: 0xffffffff80200000,
+                size: 0x42,
+                type_: b'T',
+                name: "<ostd::Foo as core::ops::Drop>::drop".to_owned(),
+            })
+        );
+        assert_eq!(
+            parse_nm_line("ffffffff80100000 t __stext"),
+            Some(Symbol {
+                addr: 0xffffffff80100000,
+                size: 0,
+                type_: b't',
+                name: "__stext".to_owned(),
+            })
+        );
+        assert_eq!(parse_nm_line("0000000000000000 a ostd.1234-cgu.0"), None);
+    }
+
+    #[test]
+    fn encode_front_coded_names() {
+        let symbol = |addr, name: &str| Symbol {
+            addr,
+            size: 0,
+            type_: b't',
+            name: name.to_owned(),
+        };
+        let table = encode_table(&[symbol(0x1000, "aster::foo"), symbol(0x1010, "aster::bar")]);
+
+        let header_size = 16;
+        let names = &table[header_size + 2 * 8 + 2 * 4 + 4..];
+        assert_eq!(&table[..4], MAGIC);
+        assert_eq!(
+            names.len(),
+            u32::from_le_bytes(table[8..12].try_into().unwrap()) as usize
+        );
+        assert_eq!(&names[..13], b"t\x00\x0aaster::foo");
+        assert_eq!(&names[13..], b"t\x07\x03bar");
+    }
+}
@@ -1,40 +1,53 @@
 // SPDX-License-Identifier: MPL-2.0
 
-mod bin;
-mod grub;
+pub(super) mod bin;
+mod disk_image;
+pub(super) mod grub;
+mod kallsyms;
+mod offline;
 mod qcow2;
 
 use std::{
     ffi::OsString,
     path::{Path, PathBuf},
-    process,
     time::SystemTime,
 };
 
-use bin::make_elf_for_qemu;
+use bin::{make_elf_for_qemu, make_install_bzimage};
 
-use super::util::{cargo, profile_name_adapter, COMMON_CARGO_ARGS, DEFAULT_TARGET_RELPATH};
+use super::util::{
+    cargo, is_offline, profile_name_adapter, COMMON_CARGO_ARGS, DEFAULT_TARGET_RELPATH,
+};
 use crate::{
     arch::Arch,
     base_crate::{new_base_crate, BaseCrateType},
     bundle::{
         bin::{AsterBin, AsterBinType, AsterElfMeta},
         file::BundleFile,
-        Bundle,
+        initramfs, Bundle,
     },
     cli::BuildArgs,
     config::{
-        scheme::{ActionChoice, BootMethod},
+        scheme::{ActionChoice, BootMethod, DirectBootProtocol},
         Config,
     },
     error::Errno,
-    error_msg,
+    exit_with_error,
     util::{
         get_cargo_metadata, get_current_crates, get_kernel_crate, get_target_directory, CrateInfo,
         DirGuard,
     },
 };
 
+/// The flags that instrument the edges with the calls that record the PCs of
+/// the edges for the kcov device.
+const KCOV_RUSTFLAGS: &[&str] = &[
+    "-C passes=sancov-module",
+    "-C llvm-args=-sanitizer-coverage-level=3",
+    "-C llvm-args=-sanitizer-coverage-trace-pc-guard",
+    "-C llvm-args=-sanitizer-coverage-pc-table",
+];
+
 pub fn execute_build_command(config: &Config, build_args: &BuildArgs) {
     let cargo_target_directory = get_target_directory();
     let osdk_output_directory = build_args
@@ -47,7 +60,19 @@ pub fn execute_build_command(config: &Config, build_args: &BuildArgs) {
 
     let target_info = get_kernel_crate();
 
-    let bundle_path = osdk_output_directory.join(target_info.name.clone());
+    // The instrumented kernel is built into a separate bundle, so that it
+    // does not invalidate the cached bundle of the normal builds.
+    let (bundle_path, rustflags) = if build_args.kcov {
+        (
+            osdk_output_directory.join(format!("{}-kcov", target_info.name)),
+            KCOV_RUSTFLAGS,
+        )
+    } else {
+        (
+            osdk_output_directory.join(target_info.name.clone()),
+            &[][..],
+        )
+    };
 
     let action = if build_args.for_test {
         ActionChoice::Test
@@ -55,15 +80,29 @@ pub fn execute_build_command(config: &Config, build_args: &BuildArgs) {
         ActionChoice::Run
     };
 
-    let _bundle = create_base_and_cached_build(
+    let bundle = create_base_and_cached_build(
         target_info,
         bundle_path,
         &osdk_output_directory,
         &cargo_target_directory,
         config,
         action,
-        &[],
+        rustflags,
     );
+
+    if let Some(format) = build_args.disk_image {
+        if config.target_arch != Arch::X86_64 {
+            exit_with_error!(
+                Errno::Cli,
+                "Building disk images is not supported on the architecture `{}`",
+                config.target_arch
+            );
+        }
+        info!("Building the disk image");
+        let disk_image =
+            disk_image::create_disk_image(&osdk_output_directory, &bundle, config, action, format);
+        println!("Built the disk image: {}", disk_image.display());
+    }
 }
 
 pub fn create_base_and_cached_build(
@@ -130,6 +169,11 @@ pub fn do_cached_build(
     action: ActionChoice,
     rustflags: &[&str],
 ) -> Bundle {
+    if is_offline() {
+        offline::check_local_components(config, action);
+    }
+
+    let config = &pack_initramfs_dir(config, action, osdk_output_directory.as_ref());
     let (build, boot) = match action {
         ActionChoice::Run => (&config.run.build, &config.run.boot),
         ActionChoice::Test => (&config.test.build, &config.test.boot),
@@ -144,6 +188,7 @@ pub fn do_cached_build(
         &cargo_target_directory,
         rustflags,
     );
+    kallsyms::embed_kallsyms(aster_elf.path());
 
     // Check the existing bundle's reusability
     if let Some(existing_bundle) = get_reusable_existing_bundle(&bundle_path, config, action) {
@@ -178,6 +223,19 @@ pub fn do_cached_build(
             }
             bundle.consume_aster_bin(aster_elf);
         }
+        BootMethod::QemuDirect if boot.protocol.is_linux() => {
+            info!("Building bzImage for direct booting");
+            let bzimage_dir = osdk_output_directory.as_ref().join("bzimage");
+            std::fs::create_dir_all(&bzimage_dir).unwrap();
+            let bzimage = make_install_bzimage(
+                &bzimage_dir,
+                &osdk_output_directory,
+                &aster_elf,
+                boot.protocol == DirectBootProtocol::LinuxLegacy,
+                build.encoding.clone(),
+            );
+            bundle.consume_aster_bin(bzimage);
+        }
         BootMethod::QemuDirect => {
             let qemu_elf = make_elf_for_qemu(&osdk_output_directory, &aster_elf, build.strip_elf);
             bundle.consume_aster_bin(qemu_elf);
@@ -187,6 +245,25 @@ pub fn do_cached_build(
     bundle
 }
 
+/// Packs the initramfs into a CPIO archive if a directory is given as the initramfs.
+///
+/// The returned configuration refers to the archive instead of the directory.
+fn pack_initramfs_dir(
+    config: &Config,
+    action: ActionChoice,
+    osdk_output_directory: &Path,
+) -> Config {
+    let mut config = config.clone();
+    let boot = match action {
+        ActionChoice::Run => &mut config.run.boot,
+        ActionChoice::Test => &mut config.test.boot,
+    };
+    if let Some(initramfs) = boot.initramfs.as_mut().filter(|path| path.is_dir()) {
+        *initramfs = initramfs::build_from_dir(initramfs, &osdk_output_directory.join("initramfs"));
+    }
+    config
+}
+
 fn build_kernel_elf(
     arch: Arch,
     profile: &str,
@@ -197,7 +274,14 @@ fn build_kernel_elf(
     rustflags: &[&str],
 ) -> AsterBin {
     let target_os_string = OsString::from(&arch.triple());
-    let rustc_linker_script_arg = format!("-C link-arg=-T{}.ld", arch);
+    let Some(linker_script) = arch.linker_script() else {
+        exit_with_error!(
+            Errno::Cli,
+            "Building for the architecture `{}` is not supported yet",
+            arch
+        );
+    };
+    let rustc_linker_script_arg = format!("-C link-arg=-T{}", linker_script);
 
     let env_rustflags = std::env::var("RUSTFLAGS").unwrap_or_default();
     let mut rustflags = Vec::from(rustflags);
@@ -217,6 +301,9 @@ fn build_kernel_elf(
         // or exception handlers may overwrite kernel data in the red zone. Therefore, we disable
         // this optimization.
         "-C no-redzone=y",
+        // Detect stack buffer overflows with canaries. The canary and the failure handler
+        // are provided by OSTD.
+        "-Z stack-protector=strong",
     ]);
 
     if matches!(arch, Arch::X86_64) {
@@ -249,8 +336,7 @@ fn build_kernel_elf(
 
     let status = command.status().unwrap();
     if !status.success() {
-        error_msg!("Cargo build failed");
-        process::exit(Errno::ExecuteCommand as _);
+        exit_with_error!(Errno::ExecuteCommand, "Cargo build failed");
     }
 
     let aster_bin_path = cargo_target_directory
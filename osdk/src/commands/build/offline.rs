@@ -0,0 +1,217 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Verification of the components required by an offline build.
+//!
+//! An offline build cannot download anything, so all the components must be present locally
+//! before building. Instead of failing in the middle of the build, all the missing components
+//! are reported at once together with how to pre-fetch them.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    config::{
+        scheme::{firmware_files, ActionChoice, BootMethod, BootProtocol},
+        Config,
+    },
+    diagnostic::Diagnostic,
+    error::Errno,
+};
+
+/// A component that is required by the build but is not available locally.
+struct MissingComponent {
+    /// What is missing and why it is needed.
+    description: String,
+    /// How to pre-fetch the component while online.
+    fetch_help: String,
+}
+
+/// Verifies that all the components required by the build are available locally.
+///
+/// If any of them is missing, this function reports the missing components and exits.
+pub fn check_local_components(config: &Config, action: ActionChoice) {
+    let action = match action {
+        ActionChoice::Run => &config.run,
+        ActionChoice::Test => &config.test,
+    };
+
+    let mut missing = Vec::new();
+    check_rust_src(&mut missing);
+    check_crate_dependencies(&mut missing);
+
+    match action.boot.method {
+        BootMethod::GrubRescueIso | BootMethod::GrubQcow2 => {
+            check_executable(&action.grub.grub_mkrescue, "GRUB", &mut missing);
+            check_executable(Path::new("xorriso"), "xorriso", &mut missing);
+            if action.boot.method == BootMethod::GrubQcow2 {
+                check_executable(Path::new("qemu-img"), "QEMU", &mut missing);
+            }
+            if action.grub.boot_protocol == BootProtocol::Linux {
+                check_bzimage_setup(&mut missing);
+            }
+        }
+        BootMethod::QemuDirect if action.boot.protocol.is_linux() => {
+            check_bzimage_setup(&mut missing);
+        }
+        BootMethod::QemuDirect => {}
+    }
+
+    check_firmware(&action.qemu.args, &mut missing);
+
+    if missing.is_empty() {
+        return;
+    }
+
+    let mut message = String::from("Some components required by the offline build are missing:");
+    let mut help = String::from("pre-fetch them while online:");
+    for component in missing {
+        message.push_str(&format!("\n  - {}", component.description));
+        help.push_str(&format!("\n    - {}", component.fetch_help));
+    }
+    Diagnostic::error(message)
+        .with_code(Errno::MissingComponent)
+        .with_help(help)
+        .emit_and_exit();
+}
+
+/// Checks the toolchain and the source of the standard library, which is required by
+/// `-Zbuild-std`.
+fn check_rust_src(missing: &mut Vec<MissingComponent>) {
+    let sysroot = Command::new("rustc")
+        .args(["--print", "sysroot"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()));
+    let Some(sysroot) = sysroot else {
+        missing.push(MissingComponent {
+            description: "the Rust toolchain pinned by `rust-toolchain.toml`".to_owned(),
+            fetch_help: "install the toolchain with `rustup toolchain install`".to_owned(),
+        });
+        return;
+    };
+
+    let library_dir = sysroot.join("lib/rustlib/src/rust/library");
+    if !library_dir.join("core").is_dir() {
+        missing.push(MissingComponent {
+            description: format!(
+                "the source of the standard library (`{}` is not found)",
+                library_dir.display()
+            ),
+            fetch_help: "install it with `rustup component add rust-src`".to_owned(),
+        });
+    }
+}
+
+/// Checks that the dependencies of the crates can be resolved with the local Cargo registry.
+fn check_crate_dependencies(missing: &mut Vec<MissingComponent>) {
+    let output = Command::new("cargo")
+        .args(["metadata", "--format-version", "1", "--offline"])
+        .output();
+    let error = match output {
+        Ok(output) if output.status.success() => return,
+        Ok(output) => String::from_utf8_lossy(&output.stderr)
+            .lines()
+            .find(|line| line.starts_with("error"))
+            .unwrap_or("`cargo metadata --offline` failed")
+            .to_owned(),
+        Err(err) => format!("failed to launch `cargo`: {}", err),
+    };
+
+    missing.push(MissingComponent {
+        description: format!("the crate dependencies ({})", error),
+        fetch_help: "download them with `cargo fetch` in the workspace, \
+                     or vendor them with `cargo vendor`"
+            .to_owned(),
+    });
+}
+
+/// Checks the `linux-bzimage-setup` crate, which is installed to make the `bzImage`.
+fn check_bzimage_setup(missing: &mut Vec<MissingComponent>) {
+    // The crate is built from the local source during the development of OSDK.
+    if matches!(option_env!("OSDK_LOCAL_DEV"), Some("1")) {
+        return;
+    }
+
+    let crate_file = format!("linux-bzimage-setup-{}.crate", env!("CARGO_PKG_VERSION"));
+    let is_cached = cargo_home()
+        .join("registry/cache")
+        .read_dir()
+        .into_iter()
+        .flatten()
+        .flatten()
+        .any(|registry| registry.path().join(&crate_file).is_file());
+    if !is_cached {
+        missing.push(MissingComponent {
+            description: format!(
+                "the `linux-bzimage-setup` crate of version {} for making the `bzImage`",
+                env!("CARGO_PKG_VERSION")
+            ),
+            fetch_help: "build once with the same boot protocol to cache the crate".to_owned(),
+        });
+    }
+}
+
+/// Checks the firmware files (e.g., OVMF) that are specified in the QEMU arguments.
+fn check_firmware(qemu_args: &str, missing: &mut Vec<MissingComponent>) {
+    for file in firmware_files(qemu_args) {
+        if !Path::new(&file).is_file() {
+            missing.push(MissingComponent {
+                description: format!("the firmware `{}` specified in the QEMU arguments", file),
+                fetch_help: format!("install the firmware (e.g., OVMF) at `{}`", file),
+            });
+        }
+    }
+}
+
+/// Checks that the program can be found, either at the path or in `PATH`.
+fn check_executable(program: &Path, package: &str, missing: &mut Vec<MissingComponent>) {
+    let is_found = if program.components().count() > 1 {
+        program.is_file()
+    } else {
+        env::var_os("PATH")
+            .is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+    };
+
+    if !is_found {
+        missing.push(MissingComponent {
+            description: format!("the `{}` executable", program.display()),
+            fetch_help: format!("install {} to provide `{}`", package, program.display()),
+        });
+    }
+}
+
+fn cargo_home() -> PathBuf {
+    env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cargo")))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_firmware_in_qemu_args() {
+        let mut missing = Vec::new();
+        check_firmware(
+            "-m 8G -bios /nonexistent/OVMF.fd \
+             -drive if=pflash,format=raw,unit=0,readonly=on,file=/nonexistent/OVMF_CODE.fd \
+             -drive if=virtio,file=/nonexistent/disk.img",
+            &mut missing,
+        );
+
+        let descriptions: Vec<_> = missing.iter().map(|c| c.description.as_str()).collect();
+        assert_eq!(
+            descriptions,
+            [
+                "the firmware `/nonexistent/OVMF.fd` specified in the QEMU arguments",
+                "the firmware `/nonexistent/OVMF_CODE.fd` specified in the QEMU arguments",
+            ]
+        );
+    }
+}
@@ -0,0 +1,455 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Writing the bootable ISO image to a removable drive, e.g., a USB flash drive, to boot the
+//! kernel on a physical machine.
+//!
+//! The image made by `grub-mkrescue` is a hybrid ISO, which boots from both optical discs and
+//! hard drives, so it is written to the drive as is. Since writing to a wrong device destroys
+//! its data, the device is checked before writing: it must be a whole drive that is removable or
+//! attached via USB, not in use, and large enough for the image. The image is read back from the
+//! drive after writing to detect bad drives.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    os::unix::fs::FileTypeExt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
+
+use super::{build::create_base_and_cached_build, util::DEFAULT_TARGET_RELPATH};
+use crate::{
+    cli::BurnArgs,
+    config::{
+        scheme::{ActionChoice, BootMethod},
+        Config,
+    },
+    error::Errno,
+    exit_with_error,
+    util::{get_kernel_crate, get_target_directory},
+    warn_msg,
+};
+
+/// The size above which a drive is unlikely to be a flash drive, which is the same as the
+/// threshold of the "large drive" warning in balenaEtcher.
+const LARGE_DRIVE_SIZE: u64 = 128 * 1000 * 1000 * 1000;
+
+/// The size of the chunks in which the image is written and read back.
+const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+pub fn execute_burn_command(config: &Config, args: &BurnArgs) {
+    if config.run.boot.method != BootMethod::GrubRescueIso {
+        exit_with_error!(
+            Errno::Cli,
+            "Only the hybrid ISO image can be burned, which is made if the boot method is \
+             `grub-rescue-iso`"
+        );
+    }
+
+    // The device is probed before building, so that a wrong path fails fast.
+    let device = BlockDevice::probe(&args.device).unwrap_or_else(|msg| {
+        exit_with_error!(Errno::Cli, "{}", msg);
+    });
+
+    let cargo_target_directory = get_target_directory();
+    let osdk_output_directory = cargo_target_directory.join(DEFAULT_TARGET_RELPATH);
+    let target_info = get_kernel_crate();
+    let bundle_path = osdk_output_directory.join(&target_info.name);
+    let bundle = create_base_and_cached_build(
+        target_info,
+        &bundle_path,
+        &osdk_output_directory,
+        &cargo_target_directory,
+        config,
+        ActionChoice::Run,
+        &[],
+    );
+    let image_path = bundle.vm_image_path().unwrap();
+    let image_size = fs::metadata(&image_path).unwrap().len();
+
+    if let Err(msg) = device.check(image_size, args.force) {
+        exit_with_error!(Errno::Cli, "{}", msg);
+    }
+
+    println!(
+        "Burning {} ({}) to {}",
+        image_path.display(),
+        HumanBytes(image_size),
+        device.description()
+    );
+    if !args.yes && !confirm(&device) {
+        exit_with_error!(
+            Errno::Cli,
+            "Aborted, nothing is written to {}",
+            device.path.display()
+        );
+    }
+
+    write_image(&image_path, &device.path, image_size);
+    if !args.no_verify {
+        verify_image(&image_path, &device.path, image_size);
+    }
+
+    println!(
+        "Burned the image to {}, which can be removed safely",
+        device.path.display()
+    );
+}
+
+/// A block device to write the image to.
+#[derive(Debug)]
+struct BlockDevice {
+    /// The path of the device node, with the symbolic links (e.g., `/dev/disk/by-id/*`) resolved.
+    path: PathBuf,
+    /// The size in bytes.
+    size: u64,
+    /// The model reported by the drive, if any.
+    model: Option<String>,
+    is_partition: bool,
+    is_read_only: bool,
+    is_removable: bool,
+    is_usb: bool,
+    /// The mount points and the swap files of the device and its partitions.
+    users: Vec<String>,
+}
+
+impl BlockDevice {
+    /// Probes the device via sysfs.
+    fn probe(path: &Path) -> Result<Self, String> {
+        let path = fs::canonicalize(path)
+            .map_err(|err| format!("Cannot access the device {}: {}", path.display(), err))?;
+        let is_block_device =
+            fs::metadata(&path).is_ok_and(|metadata| metadata.file_type().is_block_device());
+        if !is_block_device {
+            return Err(format!("{} is not a block device", path.display()));
+        }
+
+        let name = path.file_name().unwrap().to_str().unwrap().to_owned();
+        let sys_path = Path::new("/sys/class/block").join(&name);
+        let read_attr = |attr: &str| {
+            fs::read_to_string(sys_path.join(attr))
+                .ok()
+                .map(|value| value.trim().to_owned())
+        };
+
+        // The size in sysfs is always in the 512-byte sectors.
+        let size = read_attr("size")
+            .and_then(|sectors| sectors.parse::<u64>().ok())
+            .ok_or_else(|| format!("Cannot read the size of {}", path.display()))?
+            * 512;
+        let model = [read_attr("device/vendor"), read_attr("device/model")]
+            .into_iter()
+            .flatten()
+            .filter(|value| !value.is_empty())
+            .reduce(|vendor, model| vendor + " " + &model);
+        // The USB drives are attached under a USB host controller in the device tree.
+        let is_usb = fs::canonicalize(&sys_path).is_ok_and(|path| {
+            path.components()
+                .any(|component| component.as_os_str().to_string_lossy().starts_with("usb"))
+        });
+
+        // The partitions are the subdirectories named after the drive, e.g., `sdb1` of `sdb`.
+        let mut devices = vec![path.clone()];
+        if let Ok(entries) = fs::read_dir(&sys_path) {
+            for entry in entries.flatten() {
+                let entry_name = entry.file_name().to_string_lossy().into_owned();
+                if entry_name.starts_with(&name) && entry.path().join("partition").exists() {
+                    devices.push(Path::new("/dev").join(entry_name));
+                }
+            }
+        }
+        let mut users = Vec::new();
+        for table in ["/proc/mounts", "/proc/swaps"] {
+            users.extend(find_users(
+                &fs::read_to_string(table).unwrap_or_default(),
+                &devices,
+            ));
+        }
+
+        Ok(Self {
+            path,
+            size,
+            model,
+            is_partition: read_attr("partition").is_some(),
+            is_read_only: read_attr("ro").as_deref() == Some("1"),
+            is_removable: read_attr("removable").as_deref() == Some("1"),
+            is_usb,
+            users,
+        })
+    }
+
+    /// Checks whether the image can be written to the device.
+    ///
+    /// With `force`, the drives that are not removable or are unusually large are also allowed.
+    fn check(&self, image_size: u64, force: bool) -> Result<(), String> {
+        let path = self.path.display();
+        if self.is_partition {
+            return Err(format!(
+                "{} is a partition, but the image must be written to the whole drive",
+                path
+            ));
+        }
+        if self.is_read_only {
+            return Err(format!("{} is read-only", path));
+        }
+        if !self.users.is_empty() {
+            return Err(format!(
+                "{} is in use by {}, unmount it first",
+                path,
+                self.users.join(", ")
+            ));
+        }
+        if self.size < image_size {
+            return Err(format!(
+                "{} ({}) is smaller than the image ({})",
+                path,
+                HumanBytes(self.size),
+                HumanBytes(image_size)
+            ));
+        }
+        if !force && !self.is_removable && !self.is_usb {
+            return Err(format!(
+                "{} is neither removable nor attached via USB, use `--force` if it is the right drive",
+                path
+            ));
+        }
+        if !force && self.size > LARGE_DRIVE_SIZE {
+            return Err(format!(
+                "{} ({}) is unusually large for a removable drive, use `--force` if it is the right drive",
+                path,
+                HumanBytes(self.size)
+            ));
+        }
+        Ok(())
+    }
+
+    fn description(&self) -> String {
+        let model = self.model.as_deref().unwrap_or("unknown model");
+        format!(
+            "{} ({}, {})",
+            self.path.display(),
+            model,
+            HumanBytes(self.size)
+        )
+    }
+}
+
+/// Finds the mount points or the swap files in the mount table (e.g., `/proc/mounts`) or the swap
+/// table (e.g., `/proc/swaps`) whose sources are the devices.
+fn find_users(table: &str, devices: &[PathBuf]) -> Vec<String> {
+    table
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let source = Path::new(fields.next()?);
+            // The sources may be symbolic links, e.g., `/dev/disk/by-uuid/*`.
+            let source = fs::canonicalize(source).unwrap_or_else(|_| source.to_owned());
+            if !devices.contains(&source) {
+                return None;
+            }
+            // The swap table has no mount points, where the source itself is reported.
+            Some(
+                fields
+                    .next()
+                    .filter(|field| field.starts_with('/'))
+                    .unwrap_or(source.to_str()?)
+                    .to_owned(),
+            )
+        })
+        .collect()
+}
+
+fn confirm(device: &BlockDevice) -> bool {
+    print!(
+        "All the data on {} will be destroyed. Continue? [y/N] ",
+        device.path.display()
+    );
+    io::stdout().flush().unwrap();
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok() && matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+fn progress_bar(len: u64, msg: &'static str) -> ProgressBar {
+    let style = ProgressStyle::with_template(
+        "{msg:9} [{bar:40}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+    )
+    .unwrap()
+    .progress_chars("#>-");
+    ProgressBar::new(len).with_style(style).with_message(msg)
+}
+
+fn open_device(path: &Path, options: &OpenOptions) -> File {
+    options.open(path).unwrap_or_else(|err| {
+        let hint = if err.kind() == io::ErrorKind::PermissionDenied {
+            ", try it as root or as a member of the `disk` group"
+        } else {
+            ""
+        };
+        exit_with_error!(
+            Errno::ExecuteCommand,
+            "Failed to open {}: {}{}",
+            path.display(),
+            err,
+            hint
+        );
+    })
+}
+
+fn write_image(image_path: &Path, device_path: &Path, image_size: u64) {
+    let mut image = File::open(image_path).unwrap();
+    let mut device = open_device(device_path, OpenOptions::new().write(true));
+
+    let progress = progress_bar(image_size, "Writing");
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    loop {
+        let len = image.read(&mut buf).unwrap();
+        if len == 0 {
+            break;
+        }
+        device.write_all(&buf[..len]).unwrap_or_else(|err| {
+            exit_with_error!(
+                Errno::ExecuteCommand,
+                "Failed to write {} at offset {}: {}",
+                device_path.display(),
+                progress.position(),
+                err
+            );
+        });
+        progress.inc(len as u64);
+    }
+
+    // The drive may be removed right after the command exits, so the data are flushed to it.
+    progress.set_message("Syncing");
+    device.sync_all().unwrap_or_else(|err| {
+        exit_with_error!(
+            Errno::ExecuteCommand,
+            "Failed to flush {}: {}",
+            device_path.display(),
+            err
+        );
+    });
+    progress.finish_with_message("Written");
+}
+
+fn verify_image(image_path: &Path, device_path: &Path, image_size: u64) {
+    // The cached pages of the device are dropped, so that the data are read from the drive.
+    let flushed = Command::new("blockdev")
+        .arg("--flushbufs")
+        .arg(device_path)
+        .status()
+        .is_ok_and(|status| status.success());
+    if !flushed {
+        warn_msg!(
+            "Failed to drop the caches of {} with `blockdev --flushbufs`, the image may be \
+             verified against the caches",
+            device_path.display()
+        );
+    }
+
+    let mut image = File::open(image_path).unwrap();
+    let mut device = open_device(device_path, OpenOptions::new().read(true));
+
+    let progress = progress_bar(image_size, "Verifying");
+    let mut expected = vec![0u8; CHUNK_SIZE];
+    let mut actual = vec![0u8; CHUNK_SIZE];
+    while progress.position() < image_size {
+        let len = (image_size - progress.position()).min(CHUNK_SIZE as u64) as usize;
+        image.read_exact(&mut expected[..len]).unwrap();
+        device.read_exact(&mut actual[..len]).unwrap_or_else(|err| {
+            exit_with_error!(
+                Errno::ExecuteCommand,
+                "Failed to read {} at offset {}: {}",
+                device_path.display(),
+                progress.position(),
+                err
+            );
+        });
+
+        if let Some(index) = expected[..len]
+            .iter()
+            .zip(&actual[..len])
+            .position(|(expected, actual)| expected != actual)
+        {
+            exit_with_error!(
+                Errno::ExecuteCommand,
+                "The image read back from {} differs at offset {}, the drive may be faulty",
+                device_path.display(),
+                progress.position() + index as u64
+            );
+        }
+        progress.inc(len as u64);
+    }
+    progress.finish_with_message("Verified");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn flash_drive() -> BlockDevice {
+        BlockDevice {
+            path: PathBuf::from("/dev/sdb"),
+            size: 16 * 1000 * 1000 * 1000,
+            model: Some("Kingston DataTraveler".to_owned()),
+            is_partition: false,
+            is_read_only: false,
+            is_removable: true,
+            is_usb: true,
+            users: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn check_device() {
+        let image_size = 64 * 1024 * 1024;
+        assert!(flash_drive().check(image_size, false).is_ok());
+        assert!(flash_drive().check(flash_drive().size + 1, true).is_err());
+
+        let partition = BlockDevice {
+            path: PathBuf::from("/dev/sdb1"),
+            is_partition: true,
+            ..flash_drive()
+        };
+        assert!(partition.check(image_size, true).is_err());
+
+        let mounted = BlockDevice {
+            users: vec!["/media/usb".to_owned()],
+            ..flash_drive()
+        };
+        assert!(mounted.check(image_size, true).is_err());
+
+        let internal_disk = BlockDevice {
+            is_removable: false,
+            is_usb: false,
+            ..flash_drive()
+        };
+        assert!(internal_disk.check(image_size, false).is_err());
+        assert!(internal_disk.check(image_size, true).is_ok());
+
+        let large_drive = BlockDevice {
+            size: 2 * LARGE_DRIVE_SIZE,
+            ..flash_drive()
+        };
+        assert!(large_drive.check(image_size, false).is_err());
+        assert!(large_drive.check(image_size, true).is_ok());
+    }
+
+    #[test]
+    fn find_device_users() {
+        let devices = [PathBuf::from("/dev/sdb"), PathBuf::from("/dev/sdb1")];
+
+        let mounts = "/dev/sda2 / ext4 rw,relatime 0 0\n\
+                      /dev/sdb1 /media/usb vfat rw,nosuid 0 0\n\
+                      tmpfs /tmp tmpfs rw 0 0\n";
+        assert_eq!(find_users(mounts, &devices), vec!["/media/usb"]);
+
+        let swaps = "Filename Type Size Used Priority\n\
+                     /dev/sdb partition 1048572 0 -2\n";
+        assert_eq!(find_users(swaps, &devices), vec!["/dev/sdb"]);
+
+        assert!(find_users(mounts, &[PathBuf::from("/dev/sdc")]).is_empty());
+    }
+}
@@ -0,0 +1,18 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{config::Config, error::Errno, exit_with_error};
+
+/// Prints the effective configuration in TOML.
+///
+/// The configuration has been validated when it is created from the manifest and the command
+/// line arguments, so only a valid configuration is printed.
+pub fn execute_check_config_command(config: &Config) {
+    let config = toml::to_string(config).unwrap_or_else(|err| {
+        exit_with_error!(
+            Errno::ParseMetadata,
+            "Cannot serialize the configuration: {}",
+            err
+        )
+    });
+    print!("{}", config);
+}
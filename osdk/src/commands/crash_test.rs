@@ -0,0 +1,425 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Testing the crash consistency of the disk images written by the kernel.
+//!
+//! In each round, the kernel boots with the configured init arguments, which
+//! run a workload that keeps writing to a disk image, and the VM is stopped at
+//! a random time. The VM is either killed, or stopped and then quit via QMP,
+//! which makes QEMU complete the in-flight requests before exiting.
+//!
+//! Then the kernel optionally boots again with the recovery init arguments,
+//! e.g., to replay the journal of the filesystem, and a checker on the host
+//! checks the disk image. The round fails if the kernel panics or the checker
+//! fails, in which case the disk image, the console output, and the output of
+//! the checker are saved for investigation, and the test stops.
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    os::unix::net::UnixStream,
+    path::Path,
+    process::{Child, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use regex::Regex;
+
+use super::{
+    build::create_base_and_cached_build,
+    fuzz::input::Rng,
+    scenario::{boot, spawn_qemu},
+    util::{decode_output, strip_ansi_escapes, DEFAULT_TARGET_RELPATH},
+};
+use crate::{
+    bundle::{panic_report::PanicReport, Bundle},
+    cli::{CrashTestArgs, StopMethod},
+    config::{scheme::ActionChoice, Config},
+    error::Errno,
+    error_msg, exit_with_error,
+    util::{get_kernel_crate, get_target_directory},
+    warn_msg,
+};
+
+/// The QMP socket of the VM, relative to the OSDK output directory.
+const QMP_SOCKET: &str = "crash-test.qmp";
+
+pub fn execute_crash_test_command(config: &Config, args: &CrashTestArgs) {
+    // The paths are resolved before OSDK changes the current directory to build the kernel.
+    let current_dir = std::env::current_dir().unwrap();
+    let image = current_dir.join(&args.image);
+    let failure_dir = current_dir.join(&args.failures);
+    if !image.is_file() {
+        exit_with_error!(
+            Errno::GetMetadata,
+            "The disk image {} does not exist",
+            image.display()
+        );
+    }
+    if args.min_delay > args.max_delay {
+        exit_with_error!(
+            Errno::Cli,
+            "The minimum delay {} ms is larger than the maximum delay {} ms",
+            args.min_delay,
+            args.max_delay
+        );
+    }
+    let ready = args.ready.as_deref().map(|pattern| {
+        Regex::new(pattern).unwrap_or_else(|err| {
+            exit_with_error!(Errno::Cli, "Invalid pattern `{}`: {}", pattern, err);
+        })
+    });
+
+    let cargo_target_directory = get_target_directory();
+    let osdk_output_directory = cargo_target_directory.join(DEFAULT_TARGET_RELPATH);
+    let target_info = get_kernel_crate();
+
+    let mut workload_config = config.clone();
+    let qmp_socket = osdk_output_directory.join(QMP_SOCKET);
+    if args.stop == StopMethod::Qmp {
+        workload_config.run.qemu.args.push_str(&format!(
+            " -qmp unix:{},server=on,wait=off",
+            qmp_socket.display()
+        ));
+    }
+    let workload_bundle = create_base_and_cached_build(
+        target_info.clone(),
+        osdk_output_directory.join(&target_info.name),
+        &osdk_output_directory,
+        &cargo_target_directory,
+        &workload_config,
+        ActionChoice::Run,
+        &[],
+    );
+    if let Err(exit_code) = workload_bundle.run_pre_hook(&workload_config, ActionChoice::Run) {
+        std::process::exit(exit_code);
+    }
+
+    // The recovery kernel is kept apart from the workload one, since the init
+    // arguments may be baked into the bundle, which would be rebuilt in each round.
+    let recovery = args.recover_args.as_ref().map(|recover_args| {
+        let mut recovery_config = config.clone();
+        let kcmdline = &mut recovery_config.run.boot.kcmdline;
+        let separator = kcmdline
+            .iter()
+            .position(|arg| arg == "--")
+            .unwrap_or(kcmdline.len());
+        kcmdline.truncate(separator);
+        kcmdline.push("--".to_owned());
+        kcmdline.extend(recover_args.split(' ').map(str::to_owned));
+
+        let bundle = create_base_and_cached_build(
+            target_info.clone(),
+            osdk_output_directory.join(format!("{}-crash-recovery", target_info.name)),
+            &osdk_output_directory,
+            &cargo_target_directory,
+            &recovery_config,
+            ActionChoice::Run,
+            &[],
+        );
+        (bundle, recovery_config)
+    });
+
+    let seed = args.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64
+    });
+    println!(
+        "Testing the crash consistency of {} in {} rounds with the seed {}",
+        image.display(),
+        args.rounds,
+        seed
+    );
+    let mut rng = Rng::new(seed);
+
+    let tester = Tester {
+        image: &image,
+        workload: (&workload_bundle, &workload_config),
+        recovery: recovery.as_ref().map(|(bundle, config)| (bundle, config)),
+        checker: &args.checker,
+        ready: ready.as_ref(),
+        stop: args.stop,
+        qmp_socket: &qmp_socket,
+        timeout: Duration::from_secs(args.timeout),
+    };
+    for round in 1..=args.rounds {
+        let delay =
+            args.min_delay + rng.below((args.max_delay - args.min_delay + 1) as usize) as u64;
+        match tester.run_round(Duration::from_millis(delay)) {
+            Ok(outcome) => println!("round {}/{} ... ok ({})", round, args.rounds, outcome),
+            Err(failure) => {
+                println!("round {}/{} ... FAILED", round, args.rounds);
+                failure.save(&failure_dir, round, &image);
+                error_msg!("Round {} failed: {}", round, failure.message);
+                std::process::exit(1);
+            }
+        }
+    }
+    println!("\ncrash test summary: {} rounds passed", args.rounds);
+}
+
+/// The parameters of the rounds.
+struct Tester<'a> {
+    image: &'a Path,
+    workload: (&'a Bundle, &'a Config),
+    recovery: Option<(&'a Bundle, &'a Config)>,
+    checker: &'a str,
+    ready: Option<&'a Regex>,
+    stop: StopMethod,
+    qmp_socket: &'a Path,
+    /// The time limit of booting the kernel until the workload is ready, and
+    /// that of the recovery.
+    timeout: Duration,
+}
+
+impl Tester<'_> {
+    /// Runs the workload, stops it after the delay, and checks the disk image.
+    ///
+    /// Returns how the workload ended on success.
+    fn run_round(&self, delay: Duration) -> Result<String, Failure> {
+        let mut failure = Failure::default();
+
+        let (bundle, config) = self.workload;
+        let _ = fs::remove_file(self.qmp_socket);
+        let mut qemu = spawn_qemu(bundle, config, Stdio::null());
+        let mut console = Console::new(qemu.stdout.take().unwrap());
+
+        let result = self.crash(&mut qemu, &mut console, delay);
+        failure.console = console.output;
+        if PanicReport::parse(&decode_output(&failure.console)).is_some() {
+            return Err(failure.with_message("the kernel panicked when running the workload"));
+        }
+        let outcome = result.map_err(|message| failure.clone().with_message(&message))?;
+
+        if let Some((bundle, config)) = self.recovery {
+            let recovered = boot(bundle, config, Some(self.timeout));
+            let (output, exit_code) = recovered.map_err(|message| {
+                failure
+                    .clone()
+                    .with_message(&format!("the recovery failed: {}", message))
+            })?;
+            failure.console.extend_from_slice(output.as_bytes());
+            if exit_code != 0 {
+                return Err(failure.with_message(&format!(
+                    "the recovery failed with the exit code {}",
+                    exit_code
+                )));
+            }
+        }
+
+        let checked = Command::new("sh")
+            .arg("-c")
+            .arg(self.checker)
+            .arg("sh")
+            .arg(self.image)
+            .stdin(Stdio::null())
+            .output()
+            .unwrap_or_else(|err| {
+                exit_with_error!(
+                    Errno::ExecuteCommand,
+                    "Cannot run the checker `{}`: {}",
+                    self.checker,
+                    err
+                );
+            });
+        failure.checker_output = [checked.stdout, checked.stderr].concat();
+        if !checked.status.success() {
+            return Err(failure.with_message(&format!(
+                "the checker reported the corruption of {} ({})",
+                self.image.display(),
+                checked.status
+            )));
+        }
+
+        Ok(outcome)
+    }
+
+    /// Waits for the workload to be ready and stops the VM after the delay.
+    ///
+    /// Returns how the workload ended.
+    fn crash(
+        &self,
+        qemu: &mut Child,
+        console: &mut Console,
+        delay: Duration,
+    ) -> Result<String, String> {
+        if let Some(ready) = self.ready {
+            let deadline = Instant::now() + self.timeout;
+            loop {
+                match console.recv_line(deadline) {
+                    Ok(line) if ready.is_match(&line) => break,
+                    Ok(_) => {}
+                    Err(RecvTimeoutError::Timeout) => {
+                        kill(qemu);
+                        return Err(format!(
+                            "the workload is not ready after {} seconds",
+                            self.timeout.as_secs()
+                        ));
+                    }
+                    Err(RecvTimeoutError::Disconnected) => {
+                        let _ = qemu.wait();
+                        return Err("the kernel exited before the workload is ready".to_owned());
+                    }
+                }
+            }
+        }
+
+        let deadline = Instant::now() + delay;
+        loop {
+            match console.recv_line(deadline) {
+                Ok(_) => {}
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    let _ = qemu.wait();
+                    return Ok("the kernel exited before it is stopped".to_owned());
+                }
+            }
+        }
+
+        let stopped = match self.stop {
+            StopMethod::Kill => {
+                kill(qemu);
+                format!("killed after {} ms", delay.as_millis())
+            }
+            StopMethod::Qmp => match quit_via_qmp(self.qmp_socket) {
+                Ok(()) => {
+                    let _ = qemu.wait();
+                    format!("stopped via QMP after {} ms", delay.as_millis())
+                }
+                Err(err) => {
+                    warn_msg!("Cannot stop the VM via QMP, killing it instead: {}", err);
+                    kill(qemu);
+                    format!("killed after {} ms", delay.as_millis())
+                }
+            },
+        };
+        console.drain();
+        Ok(stopped)
+    }
+}
+
+/// The console output of the kernel, which is read by a thread line by line.
+struct Console {
+    receiver: Receiver<Vec<u8>>,
+    /// The raw bytes of the output, which may not be valid UTF-8.
+    output: Vec<u8>,
+}
+
+impl Console {
+    fn new(stdout: impl Read + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            let mut line = Vec::new();
+            while reader.read_until(b'\n', &mut line).is_ok_and(|len| len > 0) {
+                if sender.send(std::mem::take(&mut line)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            receiver,
+            output: Vec::new(),
+        }
+    }
+
+    /// Receives the next line before the deadline.
+    fn recv_line(&mut self, deadline: Instant) -> Result<String, RecvTimeoutError> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        let line = self.receiver.recv_timeout(timeout)?;
+        self.output.extend_from_slice(&line);
+        Ok(strip_ansi_escapes(decode_output(&line).trim_end()))
+    }
+
+    /// Receives the remaining lines after the VM exits.
+    fn drain(&mut self) {
+        for line in self.receiver.iter() {
+            self.output.extend_from_slice(&line);
+        }
+    }
+}
+
+fn kill(qemu: &mut Child) {
+    let _ = qemu.kill();
+    let _ = qemu.wait();
+}
+
+/// Stops the VM and then quits QEMU via the QMP socket.
+///
+/// Stopping the VM first makes sure that the guest issues no more requests,
+/// while the in-flight requests are completed before QEMU exits.
+fn quit_via_qmp(socket: &Path) -> std::io::Result<()> {
+    let stream = UnixStream::connect(socket)?;
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    // The greeting.
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+
+    for command in ["qmp_capabilities", "stop", "quit"] {
+        writeln!(writer, "{{\"execute\": \"{}\"}}", command)?;
+        // Skips the asynchronous events until the response.
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                // QEMU may exit before responding to `quit`.
+                return if command == "quit" {
+                    Ok(())
+                } else {
+                    Err(std::io::ErrorKind::UnexpectedEof.into())
+                };
+            }
+            let response: serde_json::Value = serde_json::from_str(&line)?;
+            if let Some(error) = response.get("error") {
+                return Err(std::io::Error::other(format!(
+                    "`{}` failed: {}",
+                    command, error
+                )));
+            }
+            if response.get("return").is_some() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A failed round.
+#[derive(Clone, Default)]
+struct Failure {
+    message: String,
+    /// The console output of the kernel, including that of the recovery.
+    console: Vec<u8>,
+    /// The standard output and the standard error of the checker.
+    checker_output: Vec<u8>,
+}
+
+impl Failure {
+    fn with_message(mut self, message: &str) -> Self {
+        self.message = message.to_owned();
+        self
+    }
+
+    /// Saves the console output, the output of the checker, and a copy of the
+    /// disk image to the directory.
+    fn save(&self, failure_dir: &Path, round: u64, image: &Path) {
+        let prefix = failure_dir.join(format!("round-{}", round));
+        let result = fs::create_dir_all(failure_dir)
+            .and_then(|()| fs::write(prefix.with_extension("log"), &self.console))
+            .and_then(|()| fs::write(prefix.with_extension("check"), &self.checker_output))
+            .and_then(|()| fs::copy(image, prefix.with_extension("img")));
+        match result {
+            Ok(_) => println!("Saved the failure to {}", failure_dir.display()),
+            Err(err) => warn_msg!(
+                "Cannot save the failure to {}: {}",
+                failure_dir.display(),
+                err
+            ),
+        }
+    }
+}
@@ -1,28 +1,155 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use std::{
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+};
+
+use super::{
+    build::create_base_and_cached_build,
+    run::{enable_gdb_server, gdb},
+    util::{bin_file_name, profile_name_adapter, DEFAULT_TARGET_RELPATH},
+};
 use crate::{
+    bundle::qemu_install_help,
     cli::DebugArgs,
-    commands::util::bin_file_name,
+    config::{
+        scheme::{ActionChoice, Debugger},
+        Config,
+    },
+    diagnostic::exit_on_launch_failure,
+    error::Errno,
+    exit_with_error,
     util::{get_kernel_crate, get_target_directory},
 };
-use std::process::Command;
-
-pub fn execute_debug_command(_profile: &str, args: &DebugArgs) {
-    let remote = &args.remote;
-
-    let file_path = get_target_directory()
-        .join("osdk")
-        .join(get_kernel_crate().name)
-        .join(bin_file_name());
-    println!("Debugging {}", file_path.display());
-
-    let mut gdb = Command::new("gdb");
-    gdb.args([
-        format!("{}", file_path.display()).as_str(),
-        "-ex",
-        format!("target remote {}", remote).as_str(),
-    ]);
-    gdb.status().unwrap();
+
+pub fn execute_debug_command(config: &Config, args: &DebugArgs) {
+    let gdb_server_addr = args
+        .remote
+        .clone()
+        .unwrap_or_else(|| config.debug.gdb_server_addr.clone());
+    let debugger = args.debugger.unwrap_or(config.debug.debugger);
+    let symbol_file = kernel_symbol_file(config);
+
+    if args.attach {
+        if debugger == Debugger::None {
+            exit_with_error!(
+                Errno::Cli,
+                "A debugger is required to attach to a running GDB server"
+            );
+        }
+        let status = debugger_command(debugger, &symbol_file, &gdb_server_addr)
+            .status()
+            .unwrap();
+        process::exit(status.code().unwrap_or(0));
+    }
+
+    let cargo_target_directory = get_target_directory();
+    let osdk_output_directory = cargo_target_directory.join(DEFAULT_TARGET_RELPATH);
+    let target_info = get_kernel_crate();
+
+    // The kernel must not start running before the debugger attaches,
+    // otherwise the early boot code cannot be debugged.
+    let mut config = config.clone();
+    enable_gdb_server(&mut config, &gdb_server_addr, true);
+
+    let default_bundle_directory = osdk_output_directory.join(&target_info.name);
+    let bundle = create_base_and_cached_build(
+        target_info,
+        default_bundle_directory,
+        &osdk_output_directory,
+        &cargo_target_directory,
+        &config,
+        ActionChoice::Run,
+        &[],
+    );
+
+    if debugger == Debugger::None {
+        println!(
+            "Waiting for a debugger to attach to \"{}\" with the symbol file \"{}\"",
+            gdb_server_addr,
+            symbol_file.display()
+        );
+        bundle.run(&config, ActionChoice::Run);
+        return;
+    }
+
+    // Check the debugger before launching QEMU, which would wait for it forever.
+    let mut debugger_cmd = debugger_command(debugger, &symbol_file, &gdb_server_addr);
+
+    let mut qemu_cmd = bundle.qemu_command(&config, ActionChoice::Run);
+    // The terminal belongs to the debugger. QEMU is put into its own process group,
+    // so that pressing Ctrl-C interrupts the kernel in the debugger instead of killing QEMU.
+    qemu_cmd.stdin(Stdio::null()).process_group(0);
+    info!("Running QEMU: {:#?}", qemu_cmd);
+    let mut qemu = qemu_cmd.spawn().unwrap_or_else(|err| {
+        exit_on_launch_failure(
+            &qemu_cmd.get_program().to_string_lossy(),
+            err,
+            &qemu_install_help(&config),
+        )
+    });
+
+    let status = debugger_cmd.status().unwrap();
+
+    // The kernel cannot make progress without the debugger in most cases,
+    // so QEMU is terminated when the debugger exits.
+    let _ = qemu.kill();
+    let _ = qemu.wait();
+
+    process::exit(status.code().unwrap_or(0));
+}
+
+/// Returns the unstripped kernel ELF, from which the debugger loads the symbols.
+fn kernel_symbol_file(config: &Config) -> PathBuf {
+    get_target_directory()
+        .join(config.target_arch.triple())
+        .join(profile_name_adapter(&config.run.build.profile))
+        .join(bin_file_name())
+}
+
+/// Returns the command that attaches the debugger to the GDB server.
+fn debugger_command(debugger: Debugger, symbol_file: &Path, gdb_server_addr: &str) -> Command {
+    let remote = match gdb::stub_type_of(gdb_server_addr) {
+        gdb::StubAddrType::Unix => gdb_server_addr.to_owned(),
+        gdb::StubAddrType::Tcp => gdb::tcp_addr_util::format_tcp_addr(gdb_server_addr),
+    };
+    println!("Debugging {}", symbol_file.display());
+
+    let (program, attach_arg, attach_cmd) = match debugger {
+        Debugger::Gdb => ("gdb", "-ex", format!("target remote {}", remote)),
+        Debugger::Lldb => {
+            if gdb::stub_type_of(gdb_server_addr) != gdb::StubAddrType::Tcp {
+                exit_with_error!(
+                    Errno::Cli,
+                    "LLDB supports only TCP GDB server addresses, use `[IP]:PORT` instead"
+                );
+            }
+            let remote = remote.trim_start_matches(':');
+            ("lldb", "-o", format!("gdb-remote {}", remote))
+        }
+        Debugger::None => unreachable!(),
+    };
+
+    if let Err(err) = Command::new(program)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .status()
+    {
+        exit_on_launch_failure(
+            &program,
+            err,
+            &format!(
+                "install `{}` or set `debug.debugger` to another debugger",
+                program
+            ),
+        );
+    }
+
+    let mut command = Command::new(program);
+    command.arg(symbol_file).arg(attach_arg).arg(attach_cmd);
+    command
 }
 
 #[test]
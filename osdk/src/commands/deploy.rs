@@ -0,0 +1,426 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use super::{
+    build::{bin::make_install_bzimage, create_base_and_cached_build, grub::grub_boot_commands},
+    util::DEFAULT_TARGET_RELPATH,
+};
+use crate::{
+    bundle::file::BundleFile,
+    cli::DeployArgs,
+    config::{
+        scheme::{ActionChoice, BootProtocol},
+        Config,
+    },
+    error::Errno,
+    exit_with_error,
+    util::{get_kernel_crate, get_target_directory, hard_link_or_copy},
+    warn_msg,
+};
+
+const INITRAMFS_NAME: &str = "initramfs.cpio.gz";
+
+pub fn execute_deploy_command(config: &Config, args: &DeployArgs) {
+    let target = DeployTarget::parse(&args.target).unwrap_or_else(|msg| {
+        exit_with_error!(Errno::Cli, "{}", msg);
+    });
+
+    let cargo_target_directory = get_target_directory();
+    let osdk_output_directory = cargo_target_directory.join(DEFAULT_TARGET_RELPATH);
+    let target_info = get_kernel_crate();
+    let kernel_name = target_info.name.clone();
+    let bundle_path = osdk_output_directory.join(&target_info.name);
+    let bundle = create_base_and_cached_build(
+        target_info,
+        &bundle_path,
+        &osdk_output_directory,
+        &cargo_target_directory,
+        config,
+        ActionChoice::Run,
+        &[],
+    );
+
+    // Collect all the files to deploy into a staging directory.
+    let staging_dir = osdk_output_directory.join("deploy");
+    if staging_dir.exists() {
+        fs::remove_dir_all(&staging_dir).unwrap();
+    }
+    fs::create_dir_all(&staging_dir).unwrap();
+
+    let protocol = &config.run.grub.boot_protocol;
+    let mut files = Vec::new();
+
+    let aster_bin = bundle.aster_bin().unwrap();
+    let kernel_path = match protocol {
+        BootProtocol::Linux => {
+            let bzimage = make_install_bzimage(
+                &staging_dir,
+                &osdk_output_directory,
+                &aster_bin,
+                config.run.build.linux_x86_legacy_boot,
+                config.build.encoding.clone(),
+            );
+            bzimage.path().clone()
+        }
+        _ => aster_bin.path().clone(),
+    };
+    files.push(stage(&staging_dir, &kernel_path, &kernel_name));
+
+    let initramfs = bundle.initramfs_path().map(|path| {
+        files.push(stage(&staging_dir, path, INITRAMFS_NAME));
+        INITRAMFS_NAME
+    });
+
+    if args.with_image {
+        match bundle.vm_image_path() {
+            Some(path) => {
+                let name = path.file_name().unwrap().to_str().unwrap().to_owned();
+                files.push(stage(&staging_dir, &path, &name));
+            }
+            None => warn_msg!("The boot method does not produce a VM image to deploy"),
+        }
+    }
+
+    if args.boot_entry {
+        let kcmdline = config.run.boot.kcmdline.join(" ");
+        let (name, content) = match &target {
+            DeployTarget::Ssh { host, dir } => {
+                // GRUB looks up the files in the filesystem that holds them,
+                // which may not be the root filesystem (e.g., a separate `/boot`).
+                let (dir, mount_point) = query_remote_mount_point(host, dir);
+                let dir = relative_to_mount_point(&dir, &mount_point);
+                (
+                    "grub/custom.cfg",
+                    generate_grub_entry(&dir, &kernel_name, initramfs, &kcmdline, protocol),
+                )
+            }
+            DeployTarget::Tftp { .. } => {
+                if matches!(protocol, BootProtocol::Multiboot2) {
+                    warn_msg!("iPXE cannot boot a kernel with the Multiboot2 boot protocol");
+                }
+                (
+                    "asterinas.ipxe",
+                    generate_ipxe_script(&kernel_name, initramfs, &kcmdline),
+                )
+            }
+        };
+        let path = staging_dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        files.push(name.to_owned());
+    }
+
+    target.upload(&staging_dir, &files);
+    println!("Deployed {} file(s) to {}", files.len(), args.target);
+
+    if let Some(host) = &args.ipmi_host {
+        power_cycle_with_ipmi(host);
+    } else if let Some(cmd) = &args.power_cycle_cmd {
+        run_command(Command::new("sh").arg("-c").arg(cmd));
+    }
+}
+
+/// Copies the file into the staging directory and returns its name.
+fn stage(staging_dir: &Path, path: impl AsRef<Path>, name: &str) -> String {
+    let dest = staging_dir.join(name);
+    if path.as_ref() != dest {
+        hard_link_or_copy(path.as_ref(), &dest).unwrap();
+    }
+    name.to_owned()
+}
+
+/// Where the built artifacts are deployed to.
+#[derive(Debug, PartialEq, Eq)]
+enum DeployTarget {
+    /// A directory on a remote machine, accessed via SSH.
+    Ssh { host: String, dir: String },
+    /// A local TFTP root directory for PXE booting.
+    Tftp { root: PathBuf },
+}
+
+impl DeployTarget {
+    fn parse(uri: &str) -> Result<Self, String> {
+        if let Some(rest) = uri.strip_prefix("ssh://") {
+            match rest.split_once(':') {
+                Some((host, dir)) if !host.is_empty() && !dir.is_empty() => Ok(Self::Ssh {
+                    host: host.to_owned(),
+                    dir: dir.to_owned(),
+                }),
+                _ => Err(format!(
+                    "Invalid SSH target `{}`, expected `ssh://[USER@]HOST:DIR`",
+                    uri
+                )),
+            }
+        } else if let Some(root) = uri.strip_prefix("tftp://") {
+            if root.is_empty() {
+                return Err(format!(
+                    "Invalid TFTP target `{}`, expected `tftp://DIR`",
+                    uri
+                ));
+            }
+            Ok(Self::Tftp {
+                root: PathBuf::from(root),
+            })
+        } else {
+            Err(format!(
+                "Unsupported deployment target `{}`, expected `ssh://[USER@]HOST:DIR` or `tftp://DIR`",
+                uri
+            ))
+        }
+    }
+
+    /// Uploads the files, which are relative paths in the staging directory.
+    fn upload(&self, staging_dir: &Path, files: &[String]) {
+        match self {
+            Self::Ssh { host, dir } => {
+                let subdirs = files
+                    .iter()
+                    .filter_map(|file| Path::new(file).parent())
+                    .filter(|parent| !parent.as_os_str().is_empty())
+                    .map(|parent| format!("{}/{}", dir, parent.display()))
+                    .collect::<Vec<_>>();
+                let mkdir = ["mkdir", "-p", dir.as_str()]
+                    .into_iter()
+                    .chain(subdirs.iter().map(String::as_str));
+                run_command(
+                    Command::new("ssh")
+                        .arg(host)
+                        .arg(quote_remote_command(mkdir)),
+                );
+                // The files are written by the remote shell instead of `scp`, which
+                // does not quote the remote paths in the same way across versions.
+                for file in files {
+                    let path = staging_dir.join(file);
+                    let local_file = fs::File::open(&path).unwrap_or_else(|err| {
+                        exit_with_error!(
+                            Errno::ExecuteCommand,
+                            "Failed to open {}: {}",
+                            path.display(),
+                            err
+                        );
+                    });
+                    let remote_path = format!("{}/{}", dir, file);
+                    run_command(
+                        Command::new("ssh")
+                            .arg(host)
+                            .arg(format!(
+                                "cat > {}",
+                                quote_remote_command([remote_path.as_str()])
+                            ))
+                            .stdin(local_file),
+                    );
+                }
+            }
+            Self::Tftp { root } => {
+                for file in files {
+                    let dest = root.join(file);
+                    fs::create_dir_all(dest.parent().unwrap()).unwrap();
+                    fs::copy(staging_dir.join(file), &dest).unwrap_or_else(|err| {
+                        exit_with_error!(
+                            Errno::ExecuteCommand,
+                            "Failed to copy {} to {}: {}",
+                            file,
+                            dest.display(),
+                            err
+                        );
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Quotes the arguments as a command line for the remote shell of SSH.
+fn quote_remote_command<'a>(args: impl IntoIterator<Item = &'a str>) -> String {
+    shlex::try_join(args).unwrap_or_else(|err| {
+        exit_with_error!(Errno::Cli, "Invalid argument for the remote shell: {}", err);
+    })
+}
+
+/// Creates the directory on the remote machine and returns its absolute path
+/// and the mount point of the filesystem that holds it.
+fn query_remote_mount_point(host: &str, dir: &str) -> (String, String) {
+    let mkdir = quote_remote_command(["mkdir", "-p", dir]);
+    let cd = quote_remote_command(["cd", dir]);
+    let mut cmd = Command::new("ssh");
+    cmd.arg(host)
+        .arg(format!("{} && {} && pwd -P && stat -c %m .", mkdir, cd));
+    info!("Running {:?}", cmd);
+    let output = cmd.stderr(Stdio::inherit()).output().unwrap_or_else(|err| {
+        exit_with_error!(
+            Errno::ExecuteCommand,
+            "Failed to execute {:?}: {}",
+            cmd,
+            err
+        );
+    });
+    if !output.status.success() {
+        exit_with_error!(
+            Errno::ExecuteCommand,
+            "Command {:?} failed with status: {:?}",
+            cmd,
+            output.status
+        );
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match stdout.lines().collect::<Vec<_>>()[..] {
+        [dir, mount_point] => (dir.to_owned(), mount_point.to_owned()),
+        _ => exit_with_error!(
+            Errno::ExecuteCommand,
+            "Unexpected output of {:?}: {}",
+            cmd,
+            stdout
+        ),
+    }
+}
+
+/// Returns the path of the directory relative to the root of the filesystem
+/// mounted at `mount_point`, or an empty string if it is the root itself.
+fn relative_to_mount_point(dir: &str, mount_point: &str) -> String {
+    let dir = dir.trim_end_matches('/');
+    let relative = dir
+        .strip_prefix(mount_point.trim_end_matches('/'))
+        .unwrap_or(dir);
+    relative.to_owned()
+}
+
+/// Generates a GRUB menu entry that is picked up by the `41_custom` script
+/// of the target's GRUB configuration.
+///
+/// `dir` is relative to the root of the filesystem that holds the files.
+fn generate_grub_entry(
+    dir: &str,
+    kernel_name: &str,
+    initramfs: Option<&str>,
+    kcmdline: &str,
+    protocol: &BootProtocol,
+) -> String {
+    let (kernel_cmd, initramfs_cmd) = grub_boot_commands(protocol);
+    let kernel = format!("{}/{}", dir, kernel_name);
+    let mut entry = format!(
+        "# AUTOMATICALLY GENERATED BY `cargo osdk deploy`\n\n\
+         menuentry 'asterinas' {{\n    \
+             search --no-floppy --file --set=root {kernel}\n    \
+             {kernel_cmd} {kernel} {kcmdline}\n"
+    );
+    if let Some(initramfs) = initramfs {
+        entry += &format!("    {} {}/{}\n", initramfs_cmd, dir, initramfs);
+    }
+    entry += "    boot\n}\n";
+    entry
+}
+
+/// Generates an iPXE script that boots the kernel from the TFTP root.
+fn generate_ipxe_script(kernel_name: &str, initramfs: Option<&str>, kcmdline: &str) -> String {
+    let mut script = format!("#!ipxe\n\nkernel {} {}\n", kernel_name, kcmdline);
+    if let Some(initramfs) = initramfs {
+        script += &format!("initrd {}\n", initramfs);
+    }
+    script += "boot\n";
+    script
+}
+
+/// Power-cycles the target with `ipmitool`.
+///
+/// The user name and the password are read from the `IPMI_USER` and
+/// `IPMI_PASSWORD` environment variables.
+fn power_cycle_with_ipmi(host: &str) {
+    let mut cmd = Command::new("ipmitool");
+    cmd.args(["-I", "lanplus", "-H", host]);
+    if let Ok(user) = std::env::var("IPMI_USER") {
+        cmd.arg("-U").arg(user);
+    }
+    // `-E` lets `ipmitool` read the password from `IPMI_PASSWORD`.
+    cmd.args(["-E", "chassis", "power", "cycle"]);
+    run_command(&mut cmd);
+}
+
+fn run_command(cmd: &mut Command) {
+    info!("Running {:?}", cmd);
+    let status = cmd.status().unwrap_or_else(|err| {
+        exit_with_error!(
+            Errno::ExecuteCommand,
+            "Failed to execute {:?}: {}",
+            cmd,
+            err
+        );
+    });
+    if !status.success() {
+        exit_with_error!(
+            Errno::ExecuteCommand,
+            "Command {:?} failed with status: {:?}",
+            cmd,
+            status
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_deploy_target() {
+        assert_eq!(
+            DeployTarget::parse("ssh://root@host:/boot"),
+            Ok(DeployTarget::Ssh {
+                host: "root@host".to_owned(),
+                dir: "/boot".to_owned(),
+            })
+        );
+        assert_eq!(
+            DeployTarget::parse("tftp:///srv/tftp"),
+            Ok(DeployTarget::Tftp {
+                root: PathBuf::from("/srv/tftp"),
+            })
+        );
+        assert!(DeployTarget::parse("ssh://host").is_err());
+        assert!(DeployTarget::parse("tftp://").is_err());
+        assert!(DeployTarget::parse("ftp://host/boot").is_err());
+    }
+
+    #[test]
+    fn relative_paths_to_mount_points() {
+        assert_eq!(relative_to_mount_point("/boot", "/"), "/boot");
+        assert_eq!(relative_to_mount_point("/boot", "/boot"), "");
+        assert_eq!(relative_to_mount_point("/boot/aster/", "/boot"), "/aster");
+    }
+
+    #[test]
+    fn quote_remote_commands() {
+        assert_eq!(
+            quote_remote_command(["mkdir", "-p", "/boot/it's"]),
+            r#"mkdir -p "/boot/it's""#
+        );
+    }
+
+    #[test]
+    fn generate_boot_entries() {
+        let entry = generate_grub_entry(
+            "/boot",
+            "kernel",
+            Some(INITRAMFS_NAME),
+            "init=/bin/sh",
+            &BootProtocol::Multiboot2,
+        );
+        assert!(entry.contains("multiboot2 /boot/kernel init=/bin/sh"));
+        assert!(entry.contains("module2 --nounzip /boot/initramfs.cpio.gz"));
+
+        let entry = generate_grub_entry(
+            &relative_to_mount_point("/boot", "/boot"),
+            "kernel",
+            None,
+            "",
+            &BootProtocol::Multiboot2,
+        );
+        assert!(entry.contains("search --no-floppy --file --set=root /kernel\n"));
+
+        let script = generate_ipxe_script("kernel", None, "init=/bin/sh");
+        assert_eq!(script, "#!ipxe\n\nkernel kernel init=/bin/sh\nboot\n");
+    }
+}
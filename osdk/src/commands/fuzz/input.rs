@@ -0,0 +1,452 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The fuzzing inputs and their mutations.
+//!
+//! The formats of the inputs are defined by the executors in the guest (see
+//! `test/apps/fuzz/syscall_exec.c` and `test/apps/fuzz/netlink_exec.c`). An
+//! input is a sequence of records, i.e., calls or packets, which are the units
+//! of the structural mutations and of the minimization. The trailing bytes that
+//! do not form a complete record are ignored by the executors.
+
+use crate::cli::FuzzTarget;
+
+/// The maximum length of an input, which is the size of the input buffers of
+/// the executors.
+pub(super) const MAX_INPUT_LEN: usize = 64 * 1024;
+
+/// The maximum number of the calls run by the syscall executor.
+const MAX_CALLS: usize = 64;
+/// The maximum number of the arguments of a call.
+const MAX_ARGS: usize = 6;
+
+/// The kinds of the arguments of a call.
+const ARG_IMM: u8 = 0;
+const ARG_BUF: u8 = 1;
+const ARG_RES: u8 = 2;
+
+/// The syscall numbers are generated below this bound, which is larger than
+/// the numbers of the system calls on all the supported architectures.
+const SYSCALL_NR_BOUND: usize = 512;
+
+/// The flag that asks the netlink executor to fix up the length in the header.
+const PACKET_FIX_LEN: u8 = 0x1;
+
+/// The length of a netlink message header.
+const NLMSG_HDRLEN: usize = 16;
+/// The netlink message types of `NETLINK_ROUTE` are generated in the range.
+const RTM_TYPES: core::ops::Range<usize> = 16..128;
+/// The netlink message flags that are combined with `NLM_F_REQUEST`.
+const NLM_F_REQUEST: u16 = 0x1;
+const NLM_FLAGS: &[u16] = &[0, 0x4, 0x100, 0x200, 0x300, 0x400, 0x600, 0x800];
+
+/// The values that are likely to hit the edge cases.
+const INTERESTING_VALUES: &[u64] = &[
+    0,
+    1,
+    2,
+    3,
+    4,
+    8,
+    16,
+    32,
+    64,
+    0x7f,
+    0x80,
+    0xff,
+    0x100,
+    0x1000,
+    0x7fff,
+    0x8000,
+    0xffff,
+    0x10000,
+    0x7fff_ffff,
+    0x8000_0000,
+    0xffff_ffff,
+    0x1_0000_0000,
+    i64::MAX as u64,
+    1 << 63,
+    u64::MAX - 1,
+    u64::MAX,
+];
+
+/// A xorshift pseudorandom number generator.
+///
+/// The sequence is determined by the seed, so that a fuzzing session can be
+/// repeated.
+pub(in crate::commands) struct Rng(u64);
+
+impl Rng {
+    pub(in crate::commands) fn new(seed: u64) -> Self {
+        // The state must not be zero.
+        match seed ^ 0x9e37_79b9_7f4a_7c15 {
+            0 => Self(1),
+            state => Self(state),
+        }
+    }
+
+    pub(super) fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    /// Returns a number in `0..bound`, where `bound` must not be zero.
+    pub(in crate::commands) fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// Returns true with the probability of `1 / n`.
+    pub(super) fn one_in(&mut self, n: usize) -> bool {
+        self.below(n) == 0
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next_u64() as u8).collect()
+    }
+
+    fn interesting_value(&mut self) -> u64 {
+        INTERESTING_VALUES[self.below(INTERESTING_VALUES.len())]
+    }
+}
+
+/// Splits the input into the complete records and the trailing bytes.
+pub(super) fn split_records(target: FuzzTarget, input: &[u8]) -> (Vec<&[u8]>, &[u8]) {
+    let mut records = Vec::new();
+    let mut rest = input;
+    while let Some(len) = record_len(target, rest) {
+        if target == FuzzTarget::Syscall && records.len() == MAX_CALLS {
+            break;
+        }
+        let (record, tail) = rest.split_at(len);
+        records.push(record);
+        rest = tail;
+    }
+    (records, rest)
+}
+
+/// Returns the length of the record at the beginning of the data, or `None`
+/// if the data do not start with a complete record.
+fn record_len(target: FuzzTarget, data: &[u8]) -> Option<usize> {
+    let read_u16 = |pos: usize| Some(u16::from_le_bytes([*data.get(pos)?, *data.get(pos + 1)?]));
+
+    let len = match target {
+        FuzzTarget::Syscall => {
+            let nr_args = *data.get(2)? as usize;
+            if nr_args > MAX_ARGS {
+                return None;
+            }
+            let mut pos = 3;
+            for _ in 0..nr_args {
+                pos += match *data.get(pos)? {
+                    ARG_IMM => 1 + 8,
+                    ARG_BUF => 1 + 2 + read_u16(pos + 1)? as usize,
+                    ARG_RES => 1 + 1,
+                    _ => return None,
+                };
+            }
+            pos
+        }
+        FuzzTarget::Packet => 3 + read_u16(1)? as usize,
+    };
+    (len <= data.len()).then_some(len)
+}
+
+/// Generates an input from scratch.
+pub(super) fn generate(target: FuzzTarget, rng: &mut Rng) -> Vec<u8> {
+    let nr_records = 1 + rng.below(8);
+    (0..nr_records)
+        .flat_map(|_| generate_record(target, rng))
+        .collect()
+}
+
+fn generate_record(target: FuzzTarget, rng: &mut Rng) -> Vec<u8> {
+    match target {
+        FuzzTarget::Syscall => generate_call(rng),
+        FuzzTarget::Packet => generate_packet(rng),
+    }
+}
+
+fn generate_call(rng: &mut Rng) -> Vec<u8> {
+    let mut call = Vec::new();
+    call.extend((rng.below(SYSCALL_NR_BOUND) as u16).to_le_bytes());
+    let nr_args = rng.below(MAX_ARGS + 1);
+    call.push(nr_args as u8);
+
+    for _ in 0..nr_args {
+        match rng.below(10) {
+            0..=4 => {
+                let value = if rng.one_in(2) {
+                    rng.interesting_value()
+                } else {
+                    rng.next_u64() >> rng.below(64)
+                };
+                call.push(ARG_IMM);
+                call.extend(value.to_le_bytes());
+            }
+            5..=7 => {
+                let len = if rng.one_in(4) {
+                    rng.below(4096)
+                } else {
+                    rng.below(64)
+                };
+                call.push(ARG_BUF);
+                call.extend((len as u16).to_le_bytes());
+                call.extend(rng.bytes(len));
+            }
+            _ => {
+                // The index counts back from the previous call. The results
+                // of the recent calls (e.g., a file descriptor just opened)
+                // are more likely to be useful.
+                let index = if rng.one_in(2) {
+                    rng.below(4)
+                } else {
+                    rng.below(MAX_CALLS)
+                };
+                call.push(ARG_RES);
+                call.push(index as u8);
+            }
+        }
+    }
+    call
+}
+
+fn generate_packet(rng: &mut Rng) -> Vec<u8> {
+    // The family header (e.g., `struct ifinfomsg`) is followed by attributes.
+    let header_len = 4 * rng.below(5);
+    let mut payload = rng.bytes(header_len);
+    for _ in 0..rng.below(4) {
+        let len = rng.below(16);
+        payload.extend((4 + len as u16).to_le_bytes());
+        payload.extend((rng.below(64) as u16).to_le_bytes());
+        payload.extend(rng.bytes(len.next_multiple_of(4)));
+    }
+
+    let len = NLMSG_HDRLEN + payload.len();
+    let mut packet = vec![PACKET_FIX_LEN];
+    packet.extend((len as u16).to_le_bytes());
+    packet.extend((len as u32).to_le_bytes());
+    packet.extend(((RTM_TYPES.start + rng.below(RTM_TYPES.len())) as u16).to_le_bytes());
+    packet.extend((NLM_F_REQUEST | NLM_FLAGS[rng.below(NLM_FLAGS.len())]).to_le_bytes());
+    packet.extend((rng.next_u64() as u32).to_le_bytes());
+    packet.extend(0u32.to_le_bytes());
+    packet.extend(payload);
+    packet
+}
+
+/// Mutates the input, where `other` is another input to splice with.
+pub(super) fn mutate(target: FuzzTarget, input: &[u8], other: &[u8], rng: &mut Rng) -> Vec<u8> {
+    let mut data = input.to_vec();
+    for _ in 0..1 + rng.below(4) {
+        match rng.below(10) {
+            0..=4 => mutate_bytes(&mut data, rng),
+            5..=8 => mutate_records(target, &mut data, rng),
+            _ => data = splice(target, &data, other, rng),
+        }
+    }
+    data.truncate(MAX_INPUT_LEN);
+    data
+}
+
+/// Mutates the bytes regardless of the records.
+fn mutate_bytes(data: &mut Vec<u8>, rng: &mut Rng) {
+    if data.is_empty() {
+        let len = 1 + rng.below(16);
+        data.extend(rng.bytes(len));
+        return;
+    }
+
+    let pos = rng.below(data.len());
+    match rng.below(5) {
+        0 => data[pos] ^= 1 << rng.below(8),
+        1 => data[pos] = rng.next_u64() as u8,
+        2 => {
+            let width = [1, 2, 4, 8][rng.below(4)].min(data.len() - pos);
+            let value = rng.interesting_value().to_le_bytes();
+            data[pos..pos + width].copy_from_slice(&value[..width]);
+        }
+        3 => {
+            let len = 1 + rng.below(16);
+            let bytes = rng.bytes(len);
+            data.splice(pos..pos, bytes);
+        }
+        _ => {
+            let end = (pos + 1 + rng.below(16)).min(data.len());
+            data.drain(pos..end);
+        }
+    }
+}
+
+/// Inserts, removes, duplicates or swaps the records.
+fn mutate_records(target: FuzzTarget, data: &mut Vec<u8>, rng: &mut Rng) {
+    let (records, rest) = split_records(target, data);
+    let mut records: Vec<Vec<u8>> = records.into_iter().map(<[u8]>::to_vec).collect();
+    let rest = rest.to_vec();
+
+    let len = records.len();
+    match rng.below(4) {
+        _ if len == 0 => records.push(generate_record(target, rng)),
+        0 => records.insert(rng.below(len + 1), generate_record(target, rng)),
+        1 => {
+            records.remove(rng.below(len));
+        }
+        2 => {
+            let record = records[rng.below(len)].clone();
+            records.insert(rng.below(len + 1), record);
+        }
+        _ => records.swap(rng.below(len), rng.below(len)),
+    }
+
+    *data = records.concat();
+    data.extend(rest);
+}
+
+/// Joins the records at the beginning of the input and those at the end of
+/// the other input.
+fn splice(target: FuzzTarget, input: &[u8], other: &[u8], rng: &mut Rng) -> Vec<u8> {
+    let (records, _) = split_records(target, input);
+    let (other_records, _) = split_records(target, other);
+    let head = &records[..rng.below(records.len() + 1)];
+    let tail = &other_records[rng.below(other_records.len() + 1)..];
+    head.iter()
+        .chain(tail)
+        .copied()
+        .collect::<Vec<_>>()
+        .concat()
+}
+
+/// Minimizes the input by removing the records as long as it reproduces.
+///
+/// The records are removed in chunks, from the halves of the input to the
+/// single records. The trailing bytes are always removed, since they are
+/// ignored by the executors.
+pub(super) fn minimize(
+    target: FuzzTarget,
+    input: &[u8],
+    mut reproduces: impl FnMut(&[u8]) -> bool,
+) -> Vec<u8> {
+    let (mut records, _) = split_records(target, input);
+
+    let mut chunk = records.len().div_ceil(2);
+    while chunk > 0 {
+        let mut start = 0;
+        while start < records.len() {
+            let end = (start + chunk).min(records.len());
+            let candidate: Vec<&[u8]> = records[..start]
+                .iter()
+                .chain(&records[end..])
+                .copied()
+                .collect();
+            if reproduces(&candidate.concat()) {
+                records = candidate;
+            } else {
+                start = end;
+            }
+        }
+        chunk /= 2;
+    }
+
+    records.concat()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn split_syscall_records() {
+        let input = [
+            // close(-1)
+            &[
+                3, 0, 1, ARG_IMM, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            ][..],
+            // write(<the result of the previous call>, "hi", 2)
+            &[1, 0, 3, ARG_RES, 0, ARG_BUF, 2, 0, b'h', b'i'],
+            &[ARG_IMM, 2, 0, 0, 0, 0, 0, 0, 0],
+            // An incomplete call.
+            &[0, 0, 1, ARG_BUF, 5, 0, 1],
+        ]
+        .concat();
+        let (records, rest) = split_records(FuzzTarget::Syscall, &input);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].len(), 12);
+        assert_eq!(records[1].len(), 19);
+        assert_eq!(rest, [0, 0, 1, ARG_BUF, 5, 0, 1]);
+
+        // An invalid kind of arguments ends the program.
+        let (records, rest) = split_records(FuzzTarget::Syscall, &[0, 0, 1, 7, 0]);
+        assert!(records.is_empty());
+        assert_eq!(rest.len(), 5);
+    }
+
+    #[test]
+    fn split_packet_records() {
+        let input = [PACKET_FIX_LEN, 2, 0, 0xaa, 0xbb, 0, 0, 0, 0, 3, 0, 1];
+        let (records, rest) = split_records(FuzzTarget::Packet, &input);
+        assert_eq!(records, [&input[..5], &input[5..8]]);
+        assert_eq!(rest, [0, 3, 0, 1]);
+    }
+
+    #[test]
+    fn generate_complete_records() {
+        for target in [FuzzTarget::Syscall, FuzzTarget::Packet] {
+            let mut rng = Rng::new(1);
+            for _ in 0..1000 {
+                let input = generate(target, &mut rng);
+                let (records, rest) = split_records(target, &input);
+                assert!(!records.is_empty());
+                assert!(rest.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn generate_valid_packets() {
+        let mut rng = Rng::new(2);
+        for _ in 0..1000 {
+            let packet = generate_packet(&mut rng);
+            let len = u32::from_le_bytes(packet[3..7].try_into().unwrap()) as usize;
+            assert_eq!(len, packet.len() - 3);
+            assert_eq!(len % 4, 0);
+        }
+    }
+
+    #[test]
+    fn mutate_deterministically() {
+        for target in [FuzzTarget::Syscall, FuzzTarget::Packet] {
+            let (mut rng1, mut rng2) = (Rng::new(3), Rng::new(3));
+            let mut input = generate(target, &mut rng1);
+            let other = generate(target, &mut rng2);
+            assert_eq!(input, other);
+            for _ in 0..1000 {
+                let mutated = mutate(target, &input, &other, &mut rng1);
+                assert_eq!(mutated, mutate(target, &input, &other, &mut rng2));
+                assert!(mutated.len() <= MAX_INPUT_LEN);
+                input = mutated;
+            }
+        }
+    }
+
+    #[test]
+    fn minimize_records() {
+        let mut rng = Rng::new(4);
+        let records: Vec<_> = (0..20).map(|_| generate_packet(&mut rng)).collect();
+        let input = [records.concat(), vec![0xff]].concat();
+
+        // The input reproduces if it contains the 4th and the 13th records.
+        let contains =
+            |input: &[u8], record: &[u8]| input.windows(record.len()).any(|w| w == record);
+        let mut nr_attempts = 0;
+        let minimized = minimize(FuzzTarget::Packet, &input, |candidate| {
+            nr_attempts += 1;
+            contains(candidate, &records[3]) && contains(candidate, &records[12])
+        });
+        assert_eq!(
+            minimized,
+            [records[3].clone(), records[12].clone()].concat()
+        );
+        assert!(nr_attempts < records.len() * 2);
+    }
+}
@@ -0,0 +1,585 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Coverage-guided fuzzing of the kernel.
+//!
+//! The kernel is built with its edges instrumented by SanitizerCoverage (see
+//! `ostd/src/coverage.rs`) and booted with the guest agent (see
+//! [`super::agent`]) as the init process. The inputs are sent to the guest
+//! over the console and run one by one by an executor in `test/apps/fuzz`,
+//! which depends on the target:
+//! - `syscall`: the input is a sequence of system calls;
+//! - `packet`: the input is a sequence of netlink packets, which are parsed by
+//!   the network stack of the kernel.
+//!
+//! After an input is run, the number of the covered edges is read from
+//! `/proc/coverage`. An input that covers new edges is saved to the corpus,
+//! and the later inputs are mutated from those in the corpus (see [`input`]).
+//! Since the coverage is accumulated since the boot, the corpus is replayed
+//! whenever the kernel boots.
+//!
+//! If the kernel panics or an input does not finish in time, the input is
+//! saved to the crash directory with the report, deduplicated by the signature
+//! of the crash (see [`PanicReport::signature`]). The saved input is then
+//! minimized by removing the calls or the packets, as long as the kernel still
+//! crashes with the same signature after a fresh boot.
+
+pub(super) mod input;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Stdio},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use input::{Rng, MAX_INPUT_LEN};
+
+use super::{
+    agent::AgentSession,
+    build::create_base_and_cached_build,
+    sbom::sha256::Sha256,
+    scenario::spawn_qemu,
+    util::{to_hex, DEFAULT_TARGET_RELPATH},
+};
+use crate::{
+    bundle::{panic_report::PanicReport, Bundle},
+    cli::{FuzzArgs, FuzzTarget},
+    config::{scheme::ActionChoice, Config},
+    error::Errno,
+    exit_with_error,
+    util::{get_kernel_crate, get_target_directory},
+    warn_msg,
+};
+
+/// The flags that instrument the edges with the inline boolean flags.
+const COVERAGE_RUSTFLAGS: &[&str] = &[
+    "-C passes=sancov-module",
+    "-C llvm-args=-sanitizer-coverage-level=3",
+    "-C llvm-args=-sanitizer-coverage-inline-bool-flag",
+];
+
+/// The guest agent, which runs as the init process.
+const AGENT_PATH: &str = "/test/osdk_agent/osdk_agent";
+/// The file in the guest to which the input is written.
+const INPUT_PATH: &str = "/tmp/fuzz_input";
+/// The file in the guest that reports the coverage.
+const COVERAGE_PATH: &str = "/proc/coverage";
+
+/// The time limit of booting the kernel until the guest agent is ready.
+const BOOT_TIMEOUT: Duration = Duration::from_secs(300);
+/// The number of the runs between two status lines.
+const STATUS_INTERVAL: u64 = 100;
+
+pub fn execute_fuzz_command(config: &Config, args: &FuzzArgs) {
+    // The paths are resolved before OSDK changes the current directory to build the kernel.
+    let current_dir = std::env::current_dir().unwrap();
+    let corpus_dir = current_dir.join(&args.corpus);
+    let crash_dir = current_dir.join(&args.crashes);
+    let minimize = args.minimize.as_ref().map(|path| current_dir.join(path));
+
+    let mut config = config.clone();
+    let kcmdline = &mut config.run.boot.kcmdline;
+    let separator = kcmdline
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(kcmdline.len());
+    kcmdline.truncate(separator);
+    // An oops only kills the process by default, which the executor survives
+    // unnoticed. Let the kernel panic so that the bug is caught.
+    kcmdline.extend([
+        "panic_on_oops=1".to_owned(),
+        "--".to_owned(),
+        AGENT_PATH.to_owned(),
+    ]);
+
+    // The instrumented kernel is kept apart from the normal one, since a cached
+    // bundle is reused regardless of the flags that it is built with.
+    let cargo_target_directory = get_target_directory();
+    let osdk_output_directory = cargo_target_directory.join(DEFAULT_TARGET_RELPATH);
+    let target_info = get_kernel_crate();
+    let bundle_directory = osdk_output_directory.join(format!("{}-fuzz", target_info.name));
+    let bundle = create_base_and_cached_build(
+        target_info,
+        bundle_directory,
+        &osdk_output_directory,
+        &cargo_target_directory,
+        &config,
+        ActionChoice::Run,
+        COVERAGE_RUSTFLAGS,
+    );
+    if let Err(exit_code) = bundle.run_pre_hook(&config, ActionChoice::Run) {
+        std::process::exit(exit_code);
+    }
+
+    let runner = Runner {
+        bundle: &bundle,
+        config: &config,
+        target: args.target,
+        timeout: Duration::from_secs(args.timeout),
+    };
+    if let Some(path) = minimize {
+        minimize_file(&runner, &path);
+        return;
+    }
+
+    let seed = args.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64
+    });
+    println!(
+        "Fuzzing the {:?} target with the seed {}",
+        args.target, seed
+    );
+    let mut fuzzer = Fuzzer {
+        runner,
+        corpus: Corpus::load(&corpus_dir),
+        crash_dir,
+        minimize: !args.no_minimize,
+        rng: Rng::new(seed),
+        machine: None,
+        coverage: Coverage::default(),
+        nr_runs: 0,
+        nr_crashes: 0,
+    };
+    fuzzer.run(args.runs);
+}
+
+/// Minimizes the crashing input in the file and writes the result next to it.
+fn minimize_file(runner: &Runner, path: &Path) {
+    let input = fs::read(path).unwrap_or_else(|err| {
+        exit_with_error!(
+            Errno::GetMetadata,
+            "Cannot read the input {}: {}",
+            path.display(),
+            err
+        );
+    });
+
+    let Some(crash) = runner.reproduce(&input) else {
+        exit_with_error!(
+            Errno::RunBundle,
+            "The input {} does not crash the kernel",
+            path.display()
+        );
+    };
+    println!(
+        "Minimizing the input that crashes with: {}",
+        crash.signature
+    );
+
+    let minimized = runner.minimize(&input, &crash.signature);
+    let output_path = path.with_extension("min");
+    if let Err(err) = fs::write(&output_path, &minimized) {
+        exit_with_error!(
+            Errno::RunBundle,
+            "Cannot write the minimized input {}: {}",
+            output_path.display(),
+            err
+        );
+    }
+    println!(
+        "Minimized the input from {} to {} bytes: {}",
+        input.len(),
+        minimized.len(),
+        output_path.display()
+    );
+}
+
+/// The parameters to boot the kernel and run the inputs.
+struct Runner<'a> {
+    bundle: &'a Bundle,
+    config: &'a Config,
+    target: FuzzTarget,
+    /// The time limit of an input.
+    timeout: Duration,
+}
+
+impl Runner<'_> {
+    /// Boots the kernel and checks that the executor works.
+    ///
+    /// Returns the kernel and its coverage after the boot.
+    fn boot(&self) -> (Machine, Coverage) {
+        let mut qemu = spawn_qemu(self.bundle, self.config, Stdio::piped());
+        let session = AgentSession::new(
+            qemu.stdin.take().unwrap(),
+            qemu.stdout.take().unwrap(),
+            Some(BOOT_TIMEOUT),
+        );
+        let mut machine = Machine { qemu, session };
+
+        let result = machine
+            .session
+            .wait_ready()
+            .and_then(|()| machine.execute(self.target, &[], BOOT_TIMEOUT));
+        match result {
+            Ok(coverage) if coverage.total == 0 => {
+                machine.kill();
+                exit_with_error!(
+                    Errno::RunBundle,
+                    "No edges of the kernel are instrumented for fuzzing"
+                );
+            }
+            Ok(coverage) => (machine, coverage),
+            Err(message) => {
+                let crash = machine.into_crash(message, self.bundle);
+                exit_with_error!(
+                    Errno::RunBundle,
+                    "Cannot run the fuzzing executor in the guest: {}",
+                    crash.signature
+                );
+            }
+        }
+    }
+
+    /// Runs the input in a freshly booted kernel and returns the crash, if any.
+    fn reproduce(&self, input: &[u8]) -> Option<Crash> {
+        let (mut machine, _) = self.boot();
+        match machine.execute(self.target, input, self.timeout) {
+            Ok(_) => {
+                machine.kill();
+                None
+            }
+            Err(message) => Some(machine.into_crash(message, self.bundle)),
+        }
+    }
+
+    /// Minimizes the input that crashes the kernel with the signature.
+    fn minimize(&self, input: &[u8], signature: &str) -> Vec<u8> {
+        input::minimize(self.target, input, |candidate| {
+            self.reproduce(candidate)
+                .is_some_and(|crash| crash.signature == signature)
+        })
+    }
+}
+
+/// A booted kernel with the guest agent.
+struct Machine {
+    qemu: Child,
+    session: AgentSession<ChildStdin>,
+}
+
+impl Machine {
+    /// Runs the input with the executor and returns the coverage afterwards.
+    ///
+    /// On failure, the kernel crashes or hangs, and it should be killed.
+    fn execute(
+        &mut self,
+        target: FuzzTarget,
+        input: &[u8],
+        timeout: Duration,
+    ) -> Result<Coverage, String> {
+        let executor = match target {
+            FuzzTarget::Syscall => "/test/fuzz/syscall_exec",
+            FuzzTarget::Packet => "/test/fuzz/netlink_exec",
+        };
+
+        self.session.set_timeout(Some(timeout));
+        self.session.put(INPUT_PATH, input, 0o644)?;
+        let output = self
+            .session
+            .run(&[executor.to_owned(), INPUT_PATH.to_owned()])?;
+        if output.exit_code != 0 {
+            return Err(format!(
+                "the executor exited with code {}",
+                output.exit_code
+            ));
+        }
+        let coverage = self.session.get(COVERAGE_PATH)?;
+        Coverage::parse(&String::from_utf8_lossy(&coverage))
+            .ok_or_else(|| format!("cannot parse the coverage in {}", COVERAGE_PATH))
+    }
+
+    /// Kills the kernel.
+    fn kill(mut self) {
+        let _ = self.qemu.kill();
+        let _ = self.qemu.wait();
+    }
+
+    /// Kills the kernel after the failure and returns the crash.
+    fn into_crash(mut self, message: String, bundle: &Bundle) -> Crash {
+        let _ = self.qemu.kill();
+        let _ = self.qemu.wait();
+
+        let console = self.session.console().to_owned();
+        match PanicReport::parse(&console) {
+            Some(report) => Crash {
+                signature: report.signature(),
+                report: report.render(&bundle.elf_path()),
+                console,
+            },
+            None => Crash {
+                report: format!("{}\n", message),
+                signature: message,
+                console,
+            },
+        }
+    }
+}
+
+/// The coverage reported by the kernel.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Coverage {
+    /// The number of the edges that have been executed since the boot.
+    covered: usize,
+    /// The number of the instrumented edges.
+    total: usize,
+}
+
+impl Coverage {
+    fn parse(content: &str) -> Option<Self> {
+        let mut fields = content.split_whitespace();
+        let coverage = Self {
+            covered: fields.next()?.parse().ok()?,
+            total: fields.next()?.parse().ok()?,
+        };
+        fields.next().is_none().then_some(coverage)
+    }
+}
+
+/// A crash of the kernel.
+struct Crash {
+    /// The signature that identifies the bug, i.e., the panic signature or the
+    /// failure of the guest agent if the kernel does not panic.
+    signature: String,
+    /// The human-readable report of the crash.
+    report: String,
+    /// The console output of the kernel.
+    console: String,
+}
+
+/// The inputs that increase the coverage.
+struct Corpus {
+    dir: PathBuf,
+    inputs: Vec<Vec<u8>>,
+}
+
+impl Corpus {
+    fn load(dir: &Path) -> Self {
+        let read_inputs = || -> std::io::Result<Vec<Vec<u8>>> {
+            fs::create_dir_all(dir)?;
+            let mut paths = fs::read_dir(dir)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()?;
+            paths.retain(|path| path.is_file());
+            paths.sort();
+            paths
+                .iter()
+                .map(|path| {
+                    fs::read(path).map(|mut input| {
+                        input.truncate(MAX_INPUT_LEN);
+                        input
+                    })
+                })
+                .collect()
+        };
+        let inputs = read_inputs().unwrap_or_else(|err| {
+            exit_with_error!(
+                Errno::GetMetadata,
+                "Cannot load the corpus {}: {}",
+                dir.display(),
+                err
+            );
+        });
+
+        Self {
+            dir: dir.to_owned(),
+            inputs,
+        }
+    }
+
+    /// Adds the input, which is saved to a file named after its hash.
+    fn add(&mut self, input: Vec<u8>) {
+        let path = self.dir.join(hash_hex(&input));
+        if let Err(err) = fs::write(&path, &input) {
+            warn_msg!("Cannot save the input {}: {}", path.display(), err);
+        }
+        self.inputs.push(input);
+    }
+}
+
+struct Fuzzer<'a> {
+    runner: Runner<'a>,
+    corpus: Corpus,
+    crash_dir: PathBuf,
+    minimize: bool,
+    rng: Rng,
+    machine: Option<Machine>,
+    /// The coverage after the last input.
+    coverage: Coverage,
+    nr_runs: u64,
+    /// The number of the unique crashes found in this session.
+    nr_crashes: usize,
+}
+
+impl Fuzzer<'_> {
+    /// Runs the inputs until the number of the runs is reached, if specified.
+    fn run(&mut self, max_runs: Option<u64>) {
+        while max_runs.is_none_or(|max_runs| self.nr_runs < max_runs) {
+            let input = self.next_input();
+            self.execute(input);
+            self.nr_runs += 1;
+            if self.nr_runs % STATUS_INTERVAL == 0 {
+                self.print_status();
+            }
+        }
+
+        if let Some(machine) = self.machine.take() {
+            machine.kill();
+        }
+        self.print_status();
+    }
+
+    fn next_input(&mut self) -> Vec<u8> {
+        let target = self.runner.target;
+        let inputs = &self.corpus.inputs;
+        if inputs.is_empty() || self.rng.one_in(10) {
+            return input::generate(target, &mut self.rng);
+        }
+        let input = &inputs[self.rng.below(inputs.len())];
+        let other = &inputs[self.rng.below(inputs.len())];
+        input::mutate(target, input, other, &mut self.rng)
+    }
+
+    /// Runs the input and saves it if it covers new edges or crashes the kernel.
+    fn execute(&mut self, input: Vec<u8>) {
+        let (target, timeout) = (self.runner.target, self.runner.timeout);
+        let machine = self.machine();
+        match machine.execute(target, &input, timeout) {
+            Ok(coverage) => {
+                if coverage.covered > self.coverage.covered {
+                    self.corpus.add(input);
+                }
+                self.coverage = coverage;
+            }
+            Err(message) => {
+                let machine = self.machine.take().unwrap();
+                let crash = machine.into_crash(message, self.runner.bundle);
+                self.save_crash(&input, crash);
+            }
+        }
+    }
+
+    /// Returns the booted kernel, booting one and replaying the corpus if needed.
+    fn machine(&mut self) -> &mut Machine {
+        while self.machine.is_none() {
+            let (mut machine, mut coverage) = self.runner.boot();
+
+            let mut crashed = None;
+            for (index, input) in self.corpus.inputs.iter().enumerate() {
+                match machine.execute(self.runner.target, input, self.runner.timeout) {
+                    Ok(replayed) => coverage = replayed,
+                    Err(message) => {
+                        crashed = Some((index, message));
+                        break;
+                    }
+                }
+            }
+
+            match crashed {
+                None => {
+                    self.machine = Some(machine);
+                    self.coverage = coverage;
+                }
+                // The input is removed from the corpus, or the kernel crashes on each boot.
+                Some((index, message)) => {
+                    let input = self.corpus.inputs.remove(index);
+                    let crash = machine.into_crash(message, self.runner.bundle);
+                    self.save_crash(&input, crash);
+                }
+            }
+        }
+        self.machine.as_mut().unwrap()
+    }
+
+    /// Saves the crashing input and its report, unless the crash is known.
+    fn save_crash(&mut self, input: &[u8], crash: Crash) {
+        let name = &hash_hex(crash.signature.as_bytes())[..16];
+        let input_path = self.crash_dir.join(format!("{}.input", name));
+        if input_path.exists() {
+            return;
+        }
+        self.nr_crashes += 1;
+        println!("Found a crash: {}", crash.signature);
+
+        // The crash may depend on the earlier inputs since the boot. Then it is
+        // saved as it is, since it cannot be minimized alone.
+        let input = if !self.minimize {
+            input.to_vec()
+        } else if self
+            .runner
+            .reproduce(input)
+            .is_some_and(|reproduced| reproduced.signature == crash.signature)
+        {
+            self.runner.minimize(input, &crash.signature)
+        } else {
+            warn_msg!("The crash cannot be reproduced by the input alone");
+            input.to_vec()
+        };
+
+        let files = [
+            (input_path, input.as_slice()),
+            (
+                self.crash_dir.join(format!("{}.report", name)),
+                crash.report.as_bytes(),
+            ),
+            (
+                self.crash_dir.join(format!("{}.log", name)),
+                crash.console.as_bytes(),
+            ),
+        ];
+        let result = fs::create_dir_all(&self.crash_dir).and_then(|()| {
+            files
+                .iter()
+                .try_for_each(|(path, data)| fs::write(path, data))
+        });
+        match result {
+            Ok(()) => println!(
+                "Saved the crashing input to {}",
+                self.crash_dir.join(format!("{}.input", name)).display()
+            ),
+            Err(err) => warn_msg!(
+                "Cannot save the crash to {}: {}",
+                self.crash_dir.display(),
+                err
+            ),
+        }
+    }
+
+    fn print_status(&self) {
+        println!(
+            "runs: {}, corpus: {}, edges: {}/{}, crashes: {}",
+            self.nr_runs,
+            self.corpus.inputs.len(),
+            self.coverage.covered,
+            self.coverage.total,
+            self.nr_crashes
+        );
+    }
+}
+
+fn hash_hex(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    to_hex(&hasher.finish())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_coverage() {
+        assert_eq!(
+            Coverage::parse("12 345\n"),
+            Some(Coverage {
+                covered: 12,
+                total: 345
+            })
+        );
+        assert_eq!(Coverage::parse("12\n"), None);
+        assert_eq!(Coverage::parse("12 345 6\n"), None);
+        assert_eq!(Coverage::parse("a b\n"), None);
+    }
+}
@@ -0,0 +1,385 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Computing the expected measurements of the built image for measured boot.
+//!
+//! When the image is booted with GRUB in a TDX guest or on a machine with a TPM, GRUB measures
+//! the files that it loads into the PCR 9 and the command lines into the PCR 8, which are both
+//! mapped to the RTMR 2 of TDX. The events of the kernel, the initramfs, and the command lines
+//! can be computed from the image, so that the attestation verifiers can be provisioned with
+//! them. The other events in the same registers (e.g., the GRUB commands and modules) depend on
+//! the GRUB installation and the firmware, so the final values of the PCRs and the RTMR 2 are not
+//! computed.
+//!
+//! The kernel extends the root digests of the `verity` targets given by `dm-mod.create` into the
+//! RTMR 3, which is not extended by anyone else, so its final value is computed.
+
+mod sha384;
+
+use std::{fs, fs::File, io::Read, path::Path};
+
+use serde_json::{json, Value};
+
+use self::sha384::Sha384;
+use super::{
+    build::create_base_and_cached_build,
+    sbom::sha256::Sha256,
+    util::{to_hex, DEFAULT_TARGET_RELPATH},
+};
+use crate::{
+    cli::MeasureArgs,
+    config::{
+        scheme::{ActionChoice, BootMethod, BootProtocol},
+        Config,
+    },
+    error::Errno,
+    exit_with_error,
+    util::{get_kernel_crate, get_target_directory},
+};
+
+/// The PCR into which GRUB measures the command lines.
+const GRUB_STRING_PCR: u32 = 8;
+/// The PCR into which GRUB measures the files.
+const GRUB_BINARY_PCR: u32 = 9;
+/// The RTMR to which the PCRs 8 to 15 are mapped in TDX.
+const GRUB_RTMR: u32 = 2;
+/// The RTMR into which the kernel extends the root digests of the `verity` targets.
+const VERITY_RTMR: u32 = 3;
+
+pub fn execute_measure_command(config: &Config, args: &MeasureArgs) {
+    if !matches!(
+        config.run.boot.method,
+        BootMethod::GrubRescueIso | BootMethod::GrubQcow2
+    ) {
+        exit_with_error!(
+            Errno::Cli,
+            "The measurements can only be computed if the boot method is `grub-rescue-iso` or \
+             `grub-qcow2`"
+        );
+    }
+
+    let cargo_target_directory = get_target_directory();
+    let osdk_output_directory = cargo_target_directory.join(DEFAULT_TARGET_RELPATH);
+    let target_info = get_kernel_crate();
+    let bundle_path = osdk_output_directory.join(&target_info.name);
+    create_base_and_cached_build(
+        target_info.clone(),
+        &bundle_path,
+        &osdk_output_directory,
+        &cargo_target_directory,
+        config,
+        ActionChoice::Run,
+        &[],
+    );
+
+    // The files are measured as they are in the image, i.e., in the root of the ISO.
+    let boot_dir = osdk_output_directory.join("iso_root").join("boot");
+    let kernel_path = format!("/boot/{}", target_info.name);
+    let initramfs_path = config
+        .run
+        .boot
+        .initramfs
+        .as_ref()
+        .map(|_| "/boot/initramfs.cpio.gz".to_owned());
+
+    let mut events = Vec::new();
+    events.push(file_event(&kernel_path, &boot_dir.join(&target_info.name)));
+    if let Some(initramfs_path) = &initramfs_path {
+        events.push(file_event(
+            initramfs_path,
+            &boot_dir.join("initramfs.cpio.gz"),
+        ));
+    }
+    events.push(string_event(
+        "kernel_cmdline",
+        &grub_cmdline(&kernel_path, &config.run.boot.kcmdline),
+    ));
+    // The `module` commands of Multiboot have their own command lines, while `initrd` has not.
+    if let Some(initramfs_path) =
+        initramfs_path.filter(|_| config.run.grub.boot_protocol != BootProtocol::Linux)
+    {
+        events.push(string_event("module_cmdline", &initramfs_path));
+    }
+    let verity_events = verity_root_digests(&config.run.boot.kcmdline)
+        .into_iter()
+        .map(verity_event)
+        .collect::<Vec<_>>();
+    let rtmr3 = replay(verity_events.iter().map(|event| &event.sha384));
+    events.extend(verity_events);
+
+    let document = json!({
+        "image": target_info.name,
+        "version": target_info.version,
+        "boot_method": config.run.boot.method,
+        "events": events.iter().map(Event::render).collect::<Vec<_>>(),
+        "rtmr3": to_hex(&rtmr3),
+    });
+
+    let output = args.output.clone().unwrap_or_else(|| {
+        osdk_output_directory.join(format!("{}.measurements.json", target_info.name))
+    });
+    let document = serde_json::to_string_pretty(&document).unwrap() + "\n";
+    fs::write(&output, document).unwrap_or_else(|err| {
+        exit_with_error!(
+            Errno::Cli,
+            "Cannot write the measurements to {}: {}",
+            output.display(),
+            err
+        )
+    });
+
+    for event in events.iter() {
+        match event.pcr {
+            Some(pcr) => println!("PCR {:<2} RTMR {}  {}", pcr, event.rtmr, event.description),
+            None => println!("        RTMR {}  {}", event.rtmr, event.description),
+        }
+        if let Some(sha256) = &event.sha256 {
+            println!("    sha256: {}", to_hex(sha256));
+        }
+        println!("    sha384: {}", to_hex(&event.sha384));
+    }
+    println!("RTMR 3: {}", to_hex(&rtmr3));
+    println!("Generated the measurements at {}", output.display());
+}
+
+/// An event that extends a PCR of the TPM and an RTMR of TDX.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Event {
+    /// The PCR, if the event is also measured with the TPM.
+    pcr: Option<u32>,
+    rtmr: u32,
+    /// The description of the event in the event log.
+    description: String,
+    /// The digest that extends the PCR.
+    sha256: Option<[u8; 32]>,
+    /// The digest that extends the RTMR.
+    sha384: [u8; sha384::DIGEST_SIZE],
+}
+
+impl Event {
+    fn render(&self) -> Value {
+        json!({
+            "pcr": self.pcr,
+            "rtmr": self.rtmr,
+            "description": self.description,
+            "sha256": self.sha256.as_ref().map(|digest| to_hex(digest)),
+            "sha384": to_hex(&self.sha384),
+        })
+    }
+}
+
+/// Returns the event of a file that GRUB loads, whose digests are the digests of the contents.
+fn file_event(path_in_image: &str, path: &Path) -> Event {
+    let (sha256, sha384) = hash_file(path).unwrap_or_else(|err| {
+        exit_with_error!(
+            Errno::GetMetadata,
+            "Cannot read {} for the measurements: {}",
+            path.display(),
+            err
+        )
+    });
+    Event {
+        pcr: Some(GRUB_BINARY_PCR),
+        rtmr: GRUB_RTMR,
+        description: path_in_image.to_owned(),
+        sha256: Some(sha256),
+        sha384,
+    }
+}
+
+/// Returns the event of a command line that GRUB measures, whose digests are the digests of the
+/// command line without the prefix.
+fn string_event(prefix: &str, string: &str) -> Event {
+    let mut sha256 = Sha256::new();
+    sha256.update(string.as_bytes());
+    let mut sha384 = Sha384::new();
+    sha384.update(string.as_bytes());
+    Event {
+        pcr: Some(GRUB_STRING_PCR),
+        rtmr: GRUB_RTMR,
+        description: format!("{}: {}", prefix, string),
+        sha256: Some(sha256.finish()),
+        sha384: sha384.finish(),
+    }
+}
+
+/// Returns the event of a root digest of a `verity` target, which is extended into the RTMR 3
+/// directly, followed by 16 zero bytes.
+fn verity_event(root_digest: [u8; 32]) -> Event {
+    let mut measurement = [0; sha384::DIGEST_SIZE];
+    measurement[..root_digest.len()].copy_from_slice(&root_digest);
+    Event {
+        pcr: None,
+        rtmr: VERITY_RTMR,
+        description: format!("dm-verity: {}", to_hex(&root_digest)),
+        sha256: None,
+        sha384: measurement,
+    }
+}
+
+/// Returns the value of an RTMR after it is extended with the measurements from zero.
+fn replay<'a>(
+    measurements: impl Iterator<Item = &'a [u8; sha384::DIGEST_SIZE]>,
+) -> [u8; sha384::DIGEST_SIZE] {
+    measurements.fold([0; sha384::DIGEST_SIZE], |value, measurement| {
+        let mut hasher = Sha384::new();
+        hasher.update(&value);
+        hasher.update(measurement);
+        hasher.finish()
+    })
+}
+
+/// Returns the kernel command line that GRUB measures.
+///
+/// GRUB splits the arguments of the loading command in `grub.cfg` into words like a shell, and
+/// then joins the kernel path and the words with spaces, where the words with spaces are quoted
+/// and the quotes and backslashes are escaped.
+fn grub_cmdline(kernel_path: &str, kcmdline: &[String]) -> String {
+    let words = shlex::split(&kcmdline.join(" ")).unwrap_or_else(|| {
+        exit_with_error!(
+            Errno::ParseMetadata,
+            "Cannot parse the kernel command line: {:?}",
+            kcmdline
+        )
+    });
+
+    let mut cmdline = kernel_path.to_owned();
+    for word in words {
+        cmdline.push(' ');
+        let has_space = word.contains(' ');
+        if has_space {
+            cmdline.push('"');
+        }
+        for ch in word.chars() {
+            if matches!(ch, '\\' | '\'' | '"') {
+                cmdline.push('\\');
+            }
+            cmdline.push(ch);
+        }
+        if has_space {
+            cmdline.push('"');
+        }
+    }
+    cmdline
+}
+
+/// Returns the root digests of the `verity` targets in `dm-mod.create`, in the order in which
+/// the kernel loads them.
+fn verity_root_digests(kcmdline: &[String]) -> Vec<[u8; 32]> {
+    // The position of the root digest in `<start> <length> verity <params>`.
+    const ROOT_DIGEST_POS: usize = 11;
+
+    let Some(value) = kcmdline
+        .iter()
+        .take_while(|arg| *arg != "--")
+        .filter_map(|arg| arg.strip_prefix("dm-mod.create="))
+        .last()
+    else {
+        return Vec::new();
+    };
+
+    let mut root_digests = Vec::new();
+    for device in value.trim_matches('"').split(';') {
+        // The fields are `<name>,<uuid>,<minor>,<flags>,<table>[,<table>+]`.
+        for table in device.split(',').skip(4) {
+            let params = table.split_whitespace().collect::<Vec<_>>();
+            if params.get(2) != Some(&"verity") {
+                continue;
+            }
+            let Some(root_digest) = params.get(ROOT_DIGEST_POS).and_then(|hex| parse_hex(hex))
+            else {
+                exit_with_error!(
+                    Errno::ParseMetadata,
+                    "The root digest of the verity table is invalid: {}",
+                    table
+                );
+            };
+            root_digests.push(root_digest);
+        }
+    }
+    root_digests
+}
+
+fn parse_hex(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut bytes = [0; 32];
+    for (byte, chunk) in bytes.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(chunk).unwrap(), 16).ok()?;
+    }
+    Some(bytes)
+}
+
+/// Returns the SHA-256 and SHA-384 digests of the file.
+fn hash_file(path: &Path) -> std::io::Result<([u8; 32], [u8; sha384::DIGEST_SIZE])> {
+    let mut file = File::open(path)?;
+    let mut sha256 = Sha256::new();
+    let mut sha384 = Sha384::new();
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        let len = file.read(&mut buffer)?;
+        if len == 0 {
+            break;
+        }
+        sha256.update(&buffer[..len]);
+        sha384.update(&buffer[..len]);
+    }
+    Ok((sha256.finish(), sha384.finish()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(kcmdline: &[&str]) -> Vec<String> {
+        kcmdline.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn quote_grub_cmdline() {
+        let kcmdline = args(&[
+            "SHELL=\"/bin/sh\"",
+            "LOGNAME=\"root\"",
+            "init=/usr/bin/busybox",
+            "--",
+            "sh",
+            "-c",
+            "'echo \"hi there\"'",
+        ]);
+        assert_eq!(
+            grub_cmdline("/boot/aster-nix", &kcmdline),
+            "/boot/aster-nix SHELL=/bin/sh LOGNAME=root init=/usr/bin/busybox -- sh -c \
+             \"echo \\\"hi there\\\"\""
+        );
+    }
+
+    #[test]
+    fn find_verity_root_digests() {
+        let root = "5637a317b773c4f08a8f9f3c57301a0043c3541af622ca4c8a463352e3aab761";
+        let kcmdline = vec![
+            format!(
+                "dm-mod.create=\"lin,,,rw,0 8 linear /dev/vda1 0;\
+                 root,,,ro,0 32 verity 1 /dev/vda1 /dev/vda2 4096 4096 4 0 sha256 {} -\"",
+                root
+            ),
+            "--".to_owned(),
+            "dm-mod.create=ignored".to_owned(),
+        ];
+        let root_digests = verity_root_digests(&kcmdline);
+        assert_eq!(root_digests.len(), 1);
+        assert_eq!(to_hex(&root_digests[0]), root);
+
+        assert!(verity_root_digests(&args(&["console=ttyS0"])).is_empty());
+    }
+
+    #[test]
+    fn replay_rtmr() {
+        assert_eq!(replay([].iter()), [0; sha384::DIGEST_SIZE]);
+
+        // The RTMR after being extended with a measurement of zeros.
+        let zeros = [0; sha384::DIGEST_SIZE];
+        let mut hasher = Sha384::new();
+        hasher.update(&[0; 2 * sha384::DIGEST_SIZE]);
+        assert_eq!(replay([zeros].iter()), hasher.finish());
+    }
+}
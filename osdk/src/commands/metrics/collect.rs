@@ -0,0 +1,199 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Collecting the results of the benchmarks into a metrics file.
+//!
+//! The benchmark runner (`test/benchmark/bench_linux_and_aster.sh`) writes the
+//! results of a benchmark job to `result_<suite>-<job>.json`, or to
+//! `result_<suite>-<job>-bench_results-<result>.json` if the job has multiple
+//! results. Each file is a JSON array like
+//!
+//! ```json
+//! [
+//!   { "name": "...", "unit": "MB/s", "value": "1234", "extra": "linux_result" },
+//!   { "name": "...", "unit": "MB/s", "value": "1345", "extra": "aster_result" }
+//! ]
+//! ```
+//!
+//! The Asterinas result is collected as the metric `<suite>/<job>` or
+//! `<suite>/<job>/<result>`. Whether a bigger value is better is read from the
+//! `alert.bigger_is_better` field of the job configuration in the benchmark
+//! directory.
+
+use std::{fs, path::Path};
+
+use serde_json::Value;
+
+use super::{Metric, MetricsFile};
+use crate::{cli::MetricsCollectArgs, config::Config, error::Errno, exit_with_error, warn_msg};
+
+pub fn execute_metrics_collect_command(config: &Config, args: &MetricsCollectArgs) {
+    let mut metrics: Vec<Metric> = Vec::new();
+    for path in args.files.iter() {
+        for metric in load_metrics(path, &args.bench_dir) {
+            if let Some(existing) = metrics.iter_mut().find(|m| m.name == metric.name) {
+                warn_msg!(
+                    "The metric {} is collected more than once, and the one in {} is used",
+                    metric.name,
+                    path.display()
+                );
+                *existing = metric;
+            } else {
+                metrics.push(metric);
+            }
+        }
+    }
+
+    MetricsFile::new(config, metrics).write(&args.output);
+}
+
+/// Loads the metrics in a metrics file or a benchmark result file.
+fn load_metrics(path: &Path, bench_dir: &Path) -> Vec<Metric> {
+    let content = fs::read_to_string(path).unwrap_or_else(|err| {
+        exit_with_error!(
+            Errno::GetMetadata,
+            "Cannot read {}: {}",
+            path.display(),
+            err
+        )
+    });
+    let value: Value = serde_json::from_str(&content).unwrap_or_else(|err| {
+        exit_with_error!(
+            Errno::ParseMetadata,
+            "Cannot parse {}: {}",
+            path.display(),
+            err
+        )
+    });
+
+    if value.is_object() {
+        return MetricsFile::load(path).metrics;
+    }
+
+    let Some(job) = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix("result_"))
+        .and_then(|name| name.strip_suffix(".json"))
+        .and_then(BenchJob::parse)
+    else {
+        exit_with_error!(
+            Errno::ParseMetadata,
+            "{} is neither a metrics file nor a benchmark result file named `result_<suite>-<job>.json`",
+            path.display()
+        );
+    };
+    let bigger_is_better = job.bigger_is_better(bench_dir);
+
+    let results = value.as_array().map(Vec::as_slice).unwrap_or_default();
+    let Some(result) = results
+        .iter()
+        .find(|result| result["extra"] == "aster_result")
+    else {
+        exit_with_error!(
+            Errno::ParseMetadata,
+            "{} does not contain the result of Asterinas",
+            path.display()
+        );
+    };
+
+    let value = match &result["value"] {
+        Value::Number(number) => number.as_f64(),
+        Value::String(string) => string.trim().parse().ok(),
+        _ => None,
+    };
+    let Some(value) = value else {
+        exit_with_error!(
+            Errno::ParseMetadata,
+            "The result of Asterinas in {} is not a number",
+            path.display()
+        );
+    };
+
+    vec![Metric {
+        name: job.metric_name(),
+        unit: result["unit"].as_str().unwrap_or_default().to_owned(),
+        value,
+        bigger_is_better,
+    }]
+}
+
+/// A benchmark job that produces a result file.
+#[derive(Debug, PartialEq, Eq)]
+struct BenchJob {
+    /// The path of the job directory relative to the benchmark directory.
+    dir: Vec<String>,
+    /// The name of the result if the job has multiple results.
+    result: Option<String>,
+}
+
+impl BenchJob {
+    /// Parses the file stem of a result file without the `result_` prefix.
+    fn parse(stem: &str) -> Option<Self> {
+        let mut dir: Vec<String> = stem.split('-').map(str::to_owned).collect();
+        let result = dir
+            .iter()
+            .position(|part| part == "bench_results")
+            .map(|index| dir.split_off(index)[1..].join("-"));
+        if dir.iter().chain(result.iter()).any(String::is_empty) {
+            return None;
+        }
+        Some(Self { dir, result })
+    }
+
+    fn metric_name(&self) -> String {
+        let mut name = self.dir.join("/");
+        if let Some(result) = &self.result {
+            name.push('/');
+            name.push_str(result);
+        }
+        name
+    }
+
+    /// Returns whether a bigger result is better according to the job configuration.
+    ///
+    /// A smaller result is assumed to be better if the configuration cannot be found.
+    fn bigger_is_better(&self, bench_dir: &Path) -> bool {
+        let mut path = self
+            .dir
+            .iter()
+            .fold(bench_dir.to_owned(), |path, part| path.join(part));
+        match &self.result {
+            Some(result) => path.push(format!("bench_results/{}.yaml", result)),
+            None => path.push("bench_result.yaml"),
+        }
+
+        let Ok(content) = fs::read_to_string(&path) else {
+            warn_msg!(
+                "Cannot read {}, assuming that smaller results are better",
+                path.display()
+            );
+            return false;
+        };
+        // The YAML file is simple enough to avoid a full parser.
+        content.lines().any(|line| {
+            line.trim()
+                .strip_prefix("bigger_is_better:")
+                .is_some_and(|value| value.trim() == "true")
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_bench_job() {
+        let job = BenchJob::parse("lmbench-mem_read_bw").unwrap();
+        assert_eq!(job.metric_name(), "lmbench/mem_read_bw");
+        assert_eq!(job.result, None);
+
+        let job = BenchJob::parse("schbench-smp1-bench_results-p50_rps").unwrap();
+        assert_eq!(job.metric_name(), "schbench/smp1/p50_rps");
+        assert_eq!(job.dir, ["schbench", "smp1"]);
+        assert_eq!(job.result.as_deref(), Some("p50_rps"));
+
+        assert_eq!(BenchJob::parse("lmbench--mem_read_bw"), None);
+        assert_eq!(BenchJob::parse("schbench-smp1-bench_results"), None);
+    }
+}
@@ -0,0 +1,293 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Comparing two metrics files to find the regressions.
+//!
+//! A metric regresses if it changes in the worse direction by more than its
+//! threshold, which is a percentage of the base value. The default threshold
+//! can be overridden for the metrics with a name, or with a name prefix that
+//! ends with `*`, e.g., `--metric-threshold 'lmbench/*=10'`.
+
+use super::{Metric, MetricsFile};
+use crate::{cli::MetricsCompareArgs, error::Errno, error_msg, exit_with_error, warn_msg};
+
+pub fn execute_metrics_compare_command(args: &MetricsCompareArgs) {
+    let base = MetricsFile::load(&args.base);
+    let new = MetricsFile::load(&args.new);
+
+    if base.metadata.arch != new.metadata.arch {
+        warn_msg!(
+            "The metrics are collected on different architectures ({} and {})",
+            base.metadata.arch,
+            new.metadata.arch
+        );
+    }
+    if base.metadata.config_hash != new.metadata.config_hash {
+        warn_msg!("The metrics are collected with different OSDK configurations");
+    }
+
+    let thresholds = Thresholds::parse(args.threshold, &args.metric_thresholds);
+    let comparisons = compare(&base.metrics, &new.metrics, &thresholds);
+
+    println!(
+        "{:<48} {:>14} {:>14} {:>9}  STATUS",
+        "METRIC", "BASE", "NEW", "CHANGE"
+    );
+    for comparison in comparisons.iter() {
+        let format_value = |value: Option<f64>| match value {
+            Some(value) => format!("{:.2}", value),
+            None => "-".to_owned(),
+        };
+        let change = match comparison.change {
+            Some(change) => format!("{:+.2}%", change),
+            None => "-".to_owned(),
+        };
+        println!(
+            "{:<48} {:>14} {:>14} {:>9}  {}",
+            format!("{} ({})", comparison.name, comparison.unit),
+            format_value(comparison.base),
+            format_value(comparison.new),
+            change,
+            comparison.verdict.as_str()
+        );
+    }
+
+    let nr_with_verdict = |verdict| {
+        comparisons
+            .iter()
+            .filter(|comparison| comparison.verdict == verdict)
+            .count()
+    };
+    println!(
+        "
+Metrics summary: {} regressed; {} improved; {} unchanged; {} added; {} removed",
+        nr_with_verdict(Verdict::Regressed),
+        nr_with_verdict(Verdict::Improved),
+        nr_with_verdict(Verdict::Unchanged),
+        nr_with_verdict(Verdict::Added),
+        nr_with_verdict(Verdict::Removed)
+    );
+
+    let regressions: Vec<_> = comparisons
+        .iter()
+        .filter(|comparison| comparison.verdict == Verdict::Regressed)
+        .collect();
+    for regression in regressions.iter() {
+        error_msg!(
+            "Regressed {}: {:.2} -> {:.2} {} (threshold {}%)",
+            regression.name,
+            regression.base.unwrap(),
+            regression.new.unwrap(),
+            regression.unit,
+            thresholds.get(&regression.name)
+        );
+    }
+    if !regressions.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// The result of comparing a metric.
+#[derive(Debug, Clone, PartialEq)]
+struct Comparison {
+    name: String,
+    unit: String,
+    base: Option<f64>,
+    new: Option<f64>,
+    /// The change from the base value in percentage.
+    change: Option<f64>,
+    verdict: Verdict,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verdict {
+    Regressed,
+    Improved,
+    /// The change is within the threshold.
+    Unchanged,
+    /// The metric is only in the new file.
+    Added,
+    /// The metric is only in the base file.
+    Removed,
+}
+
+impl Verdict {
+    fn as_str(self) -> &'static str {
+        match self {
+            Verdict::Regressed => "REGRESSED",
+            Verdict::Improved => "improved",
+            Verdict::Unchanged => "ok",
+            Verdict::Added => "added",
+            Verdict::Removed => "removed",
+        }
+    }
+}
+
+/// The thresholds of the metrics in percentage.
+#[derive(Debug)]
+struct Thresholds {
+    default: f64,
+    /// The thresholds of the metric names or the name prefixes that end with `*`.
+    overrides: Vec<(String, f64)>,
+}
+
+impl Thresholds {
+    /// Parses the overriding thresholds in the form of `NAME=PERCENT`.
+    fn parse(default: f64, overrides: &[String]) -> Self {
+        let overrides = overrides
+            .iter()
+            .map(|rule| {
+                let parsed = rule.rsplit_once('=').and_then(|(name, percent)| {
+                    let percent: f64 = percent.parse().ok()?;
+                    (percent >= 0.0).then(|| (name.to_owned(), percent))
+                });
+                parsed.unwrap_or_else(|| {
+                    exit_with_error!(
+                        Errno::Cli,
+                        "Invalid metric threshold `{}`, expected `NAME=PERCENT`",
+                        rule
+                    )
+                })
+            })
+            .collect();
+        Self { default, overrides }
+    }
+
+    /// Returns the threshold of the metric.
+    ///
+    /// An exact name takes precedence over the prefixes, and a longer prefix
+    /// takes precedence over a shorter one.
+    fn get(&self, name: &str) -> f64 {
+        if let Some((_, threshold)) = self.overrides.iter().rev().find(|(rule, _)| rule == name) {
+            return *threshold;
+        }
+        self.overrides
+            .iter()
+            .filter_map(|(rule, threshold)| {
+                let prefix = rule.strip_suffix('*')?;
+                name.starts_with(prefix)
+                    .then_some((prefix.len(), *threshold))
+            })
+            .max_by_key(|(len, _)| *len)
+            .map_or(self.default, |(_, threshold)| threshold)
+    }
+}
+
+/// Compares the metrics in the order of the base metrics followed by the added ones.
+fn compare(base: &[Metric], new: &[Metric], thresholds: &Thresholds) -> Vec<Comparison> {
+    let mut comparisons = Vec::new();
+
+    for base_metric in base.iter() {
+        let Some(new_metric) = new.iter().find(|metric| metric.name == base_metric.name) else {
+            comparisons.push(Comparison {
+                name: base_metric.name.clone(),
+                unit: base_metric.unit.clone(),
+                base: Some(base_metric.value),
+                new: None,
+                change: None,
+                verdict: Verdict::Removed,
+            });
+            continue;
+        };
+
+        // The change cannot be a percentage of zero.
+        let change = (base_metric.value != 0.0)
+            .then(|| (new_metric.value - base_metric.value) / base_metric.value.abs() * 100.0);
+        let worsening = match change {
+            Some(change) if new_metric.bigger_is_better => -change,
+            Some(change) => change,
+            None => 0.0,
+        };
+        let threshold = thresholds.get(&new_metric.name);
+        let verdict = if worsening > threshold {
+            Verdict::Regressed
+        } else if worsening < -threshold {
+            Verdict::Improved
+        } else {
+            Verdict::Unchanged
+        };
+
+        comparisons.push(Comparison {
+            name: new_metric.name.clone(),
+            unit: new_metric.unit.clone(),
+            base: Some(base_metric.value),
+            new: Some(new_metric.value),
+            change,
+            verdict,
+        });
+    }
+
+    for new_metric in new.iter() {
+        if base.iter().all(|metric| metric.name != new_metric.name) {
+            comparisons.push(Comparison {
+                name: new_metric.name.clone(),
+                unit: new_metric.unit.clone(),
+                base: None,
+                new: Some(new_metric.value),
+                change: None,
+                verdict: Verdict::Added,
+            });
+        }
+    }
+
+    comparisons
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn metric(name: &str, value: f64, bigger_is_better: bool) -> Metric {
+        Metric {
+            name: name.to_owned(),
+            unit: "ms".to_owned(),
+            value,
+            bigger_is_better,
+        }
+    }
+
+    #[test]
+    fn metric_thresholds() {
+        let thresholds = Thresholds::parse(
+            5.0,
+            &[
+                "lmbench/*=10".to_owned(),
+                "lmbench/mem_*=20".to_owned(),
+                "lmbench/mem_read_bw=1.5".to_owned(),
+            ],
+        );
+        assert_eq!(thresholds.get("boot_time"), 5.0);
+        assert_eq!(thresholds.get("lmbench/fifo_lat"), 10.0);
+        assert_eq!(thresholds.get("lmbench/mem_write_bw"), 20.0);
+        assert_eq!(thresholds.get("lmbench/mem_read_bw"), 1.5);
+    }
+
+    #[test]
+    fn compare_metrics() {
+        let base = [
+            metric("boot_time", 2.0, false),
+            metric("bw", 100.0, true),
+            metric("lat", 10.0, false),
+            metric("removed", 1.0, false),
+        ];
+        let new = [
+            metric("added", 1.0, false),
+            metric("lat", 9.0, false),
+            metric("bw", 80.0, true),
+            metric("boot_time", 2.05, false),
+        ];
+        let verdicts: Vec<_> = compare(&base, &new, &Thresholds::parse(5.0, &[]))
+            .into_iter()
+            .map(|comparison| (comparison.name, comparison.verdict))
+            .collect();
+        assert_eq!(
+            verdicts,
+            [
+                ("boot_time".to_owned(), Verdict::Unchanged),
+                ("bw".to_owned(), Verdict::Regressed),
+                ("lat".to_owned(), Verdict::Improved),
+                ("removed".to_owned(), Verdict::Removed),
+                ("added".to_owned(), Verdict::Added),
+            ]
+        );
+    }
+}
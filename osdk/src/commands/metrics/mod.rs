@@ -0,0 +1,175 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Normalized performance metrics for tracking the trends in a dashboard.
+//!
+//! A metrics file is a JSON document that contains the metadata of the
+//! measured kernel and a flat list of metrics:
+//!
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "metadata": {
+//!     "commit": "0123abcd...",
+//!     "dirty": false,
+//!     "config_hash": "89ab4567cdef0123",
+//!     "arch": "x86_64",
+//!     "timestamp": "2025-01-01T00:00:00Z"
+//!   },
+//!   "metrics": [
+//!     { "name": "boot_time", "unit": "s", "value": 1.5, "bigger_is_better": false }
+//!   ]
+//! }
+//! ```
+//!
+//! The files are written by `cargo osdk test --metrics` and `cargo osdk metrics
+//! collect`, and compared by `cargo osdk metrics compare`.
+
+mod collect;
+mod compare;
+
+use std::{fs, path::Path, process::Command};
+
+pub use self::{
+    collect::execute_metrics_collect_command, compare::execute_metrics_compare_command,
+};
+use super::{
+    sbom::{sha256::Sha256, timestamp},
+    util::to_hex,
+};
+use crate::{config::Config, error::Errno, exit_with_error};
+
+/// The version of the format of the metrics files.
+const SCHEMA_VERSION: u32 = 1;
+
+/// The contents of a metrics file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricsFile {
+    pub schema_version: u32,
+    pub metadata: Metadata,
+    pub metrics: Vec<Metric>,
+}
+
+/// The information about the measured kernel.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metadata {
+    /// The Git commit of the source tree, if it is in a Git repository.
+    pub commit: Option<String>,
+    /// Whether the source tree has uncommitted changes.
+    pub dirty: bool,
+    /// The hash of the effective OSDK configuration, which tells whether two
+    /// kernels are built and run in the same way.
+    pub config_hash: String,
+    pub arch: String,
+    /// The time when the metrics are collected in RFC 3339.
+    pub timestamp: String,
+}
+
+/// A measured value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Metric {
+    /// The name of the metric, whose components are separated by `/`,
+    /// e.g., `lmbench/mem_read_bw`.
+    pub name: String,
+    pub unit: String,
+    pub value: f64,
+    pub bigger_is_better: bool,
+}
+
+impl MetricsFile {
+    /// Creates a metrics file with the metadata of the current source tree and configuration.
+    pub fn new(config: &Config, metrics: Vec<Metric>) -> Self {
+        let (commit, dirty) = git_state();
+        Self {
+            schema_version: SCHEMA_VERSION,
+            metadata: Metadata {
+                commit,
+                dirty,
+                config_hash: config_hash(config),
+                arch: config.target_arch.to_string(),
+                timestamp: timestamp(),
+            },
+            metrics,
+        }
+    }
+
+    /// Loads a metrics file, or exits if it is not a valid one.
+    pub fn load(path: &Path) -> Self {
+        let content = fs::read_to_string(path).unwrap_or_else(|err| {
+            exit_with_error!(
+                Errno::GetMetadata,
+                "Cannot read the metrics file {}: {}",
+                path.display(),
+                err
+            )
+        });
+        let file: Self = serde_json::from_str(&content).unwrap_or_else(|err| {
+            exit_with_error!(
+                Errno::ParseMetadata,
+                "Cannot parse the metrics file {}: {}",
+                path.display(),
+                err
+            )
+        });
+        if file.schema_version != SCHEMA_VERSION {
+            exit_with_error!(
+                Errno::ParseMetadata,
+                "The metrics file {} has an unsupported schema version {}",
+                path.display(),
+                file.schema_version
+            );
+        }
+        file
+    }
+
+    /// Writes the metrics file, or exits if it cannot be written.
+    pub fn write(&self, path: &Path) {
+        let content = serde_json::to_string_pretty(self).unwrap() + "\n";
+        fs::write(path, content).unwrap_or_else(|err| {
+            exit_with_error!(
+                Errno::Cli,
+                "Cannot write the metrics file {}: {}",
+                path.display(),
+                err
+            )
+        });
+        println!(
+            "{} metrics are written to {}",
+            self.metrics.len(),
+            path.display()
+        );
+    }
+}
+
+/// Returns the current Git commit and whether there are uncommitted changes.
+fn git_state() -> (Option<String>, bool) {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    };
+
+    let Some(commit) = git(&["rev-parse", "HEAD"]) else {
+        return (None, false);
+    };
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+        .is_some_and(|status| !status.is_empty());
+    (Some(commit), dirty)
+}
+
+/// Returns the hash of the configuration in 16 hexadecimal digits.
+///
+/// The working directory is excluded so that the same configuration in
+/// different checkouts has the same hash.
+fn config_hash(config: &Config) -> String {
+    let mut config = config.clone();
+    config.work_dir = Default::default();
+
+    let mut sha256 = Sha256::new();
+    sha256.update(serde_json::to_string(&config).unwrap().as_bytes());
+    let mut hash = to_hex(&sha256.finish());
+    hash.truncate(16);
+    hash
+}
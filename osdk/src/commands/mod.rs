@@ -2,19 +2,34 @@
 
 //! This module contains subcommands of cargo-osdk.
 
+mod agent;
 mod build;
+mod burn;
+mod check_config;
+mod crash_test;
 mod debug;
+mod deploy;
+mod fuzz;
+mod measure;
+mod metrics;
 mod new;
 mod profile;
 mod run;
+mod sbom;
+mod scenario;
 mod test;
 mod util;
 
 use util::DEFAULT_TARGET_RELPATH;
 
 pub use self::{
-    build::execute_build_command, debug::execute_debug_command, new::execute_new_command,
-    profile::execute_profile_command, run::execute_run_command, test::execute_test_command,
+    build::execute_build_command, burn::execute_burn_command,
+    check_config::execute_check_config_command, crash_test::execute_crash_test_command,
+    debug::execute_debug_command, deploy::execute_deploy_command, fuzz::execute_fuzz_command,
+    measure::execute_measure_command, metrics::execute_metrics_collect_command,
+    metrics::execute_metrics_compare_command, new::execute_new_command,
+    profile::execute_profile_command, run::execute_run_command, sbom::execute_sbom_command,
+    scenario::execute_scenarios, test::execute_test_command, util::enable_offline_mode,
 };
 
 use crate::{
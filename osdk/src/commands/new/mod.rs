@@ -1,12 +1,12 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use std::{fs, path::PathBuf, process, str::FromStr};
+use std::{fs, path::PathBuf, str::FromStr};
 
 use crate::{
     cli::NewArgs,
     config::manifest::ProjectType,
     error::Errno,
-    error_msg,
+    exit_with_error,
     util::{cargo_new_lib, get_cargo_metadata, ostd_dep},
 };
 
@@ -218,8 +218,10 @@ fn check_rust_toolchain(toolchain: &toml::Table) {
     let expected_channel = expected.get("channel").unwrap().as_str().unwrap();
 
     if channel != expected_channel {
-        error_msg!("The current version of rust-toolchain.toml is not compatible with the osdk");
-        process::exit(Errno::AddRustToolchain as _);
+        exit_with_error!(
+            Errno::AddRustToolchain,
+            "The current version of rust-toolchain.toml is not compatible with the osdk"
+        );
     }
 
     let components = toolchain.get("components").unwrap().as_array().unwrap();
@@ -227,11 +229,11 @@ fn check_rust_toolchain(toolchain: &toml::Table) {
 
     for expected_component in expected_components {
         if !components.contains(expected_component) {
-            error_msg!(
+            exit_with_error!(
+                Errno::AddRustToolchain,
                 "rust-toolchain.toml does not contains {}",
                 expected_component.as_str().unwrap()
             );
-            process::exit(Errno::AddRustToolchain as _);
         }
     }
 }
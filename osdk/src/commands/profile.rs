@@ -3,31 +3,48 @@
 //! OSDK profile command implementation.
 //!
 //! The profile command is used to collect stack traces when running the target
-//! kernel in QEMU. It attaches to the GDB server initiated with [`super::run`]
-//! and collects the stack trace periodically. The collected data can be
-//! further analyzed using tools like
+//! kernel in QEMU. By default, it attaches to the GDB server initiated with
+//! [`super::run`] and collects the stack trace periodically. The collected
+//! data can be further analyzed using tools like
 //! [flame graph](https://github.com/brendangregg/FlameGraph).
+//!
+//! With `--sampler`, it instead builds and runs the kernel with the in-kernel
+//! sampling profiler enabled (`profiler.freq=<HZ>`). The kernel samples the
+//! interrupted stacks at the timer interrupts and dumps the counted stack
+//! traces to the console when it exits. The program counters are then
+//! symbolized against the kernel ELF with `addr2line`.
 
 use inferno::flamegraph;
 
+use super::{
+    build::create_base_and_cached_build,
+    test::apply_kcmd_args,
+    util::{bin_file_name, DEFAULT_TARGET_RELPATH},
+};
 use crate::{
     cli::{ProfileArgs, ProfileFormat},
-    commands::util::bin_file_name,
+    config::{scheme::ActionChoice, Config},
+    error::Errno,
+    exit_with_error,
     util::{get_kernel_crate, get_target_directory},
+    warn_msg,
 };
 use regex::Regex;
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap},
+    fmt::Write as _,
     fs::File,
-    io::{BufRead, Write},
-    path::PathBuf,
+    io::{BufRead, Read, Write},
+    path::{Path, PathBuf},
     process::{Command, Stdio},
     thread, time,
 };
 
-pub fn execute_profile_command(_profile: &str, args: &ProfileArgs) {
+pub fn execute_profile_command(config: &Config, args: &ProfileArgs) {
     if let Some(parse_input) = &args.parse {
         do_parse_stack_traces(parse_input, args);
+    } else if args.sampler {
+        do_run_sampling_profiler(config, args);
     } else {
         do_collect_stack_traces(args);
     }
@@ -55,11 +72,15 @@ macro_rules! profile_round_delimiter {
     };
 }
 
-fn do_collect_stack_traces(args: &ProfileArgs) {
-    let file_path = get_target_directory()
+fn kernel_elf_path() -> PathBuf {
+    get_target_directory()
         .join("osdk")
         .join(get_kernel_crate().name)
-        .join(bin_file_name());
+        .join(bin_file_name())
+}
+
+fn do_collect_stack_traces(args: &ProfileArgs) {
+    let file_path = kernel_elf_path();
 
     let remote = &args.remote;
     let samples = &args.samples;
@@ -138,6 +159,156 @@ fn do_collect_stack_traces(args: &ProfileArgs) {
         .serialize_to(out_args.format(), out_args.cpu_mask, out_file);
 }
 
+fn do_run_sampling_profiler(config: &Config, args: &ProfileArgs) {
+    let cargo_target_directory = get_target_directory();
+    let osdk_output_directory = cargo_target_directory.join(DEFAULT_TARGET_RELPATH);
+
+    let mut config = config.clone();
+    apply_kcmd_args(
+        &mut config.run.boot.kcmdline,
+        &[format!("profiler.freq={}", args.freq)],
+    );
+
+    // Ensure debug info added when profiling in the release profile.
+    if config.run.build.profile.contains("release") {
+        config
+            .run
+            .build
+            .override_configs
+            .push(format!("profile.{}.debug=true", config.run.build.profile));
+    }
+
+    // The kernel walks the stacks with the frame pointers.
+    let target_info = get_kernel_crate();
+    let default_bundle_directory = osdk_output_directory.join(&target_info.name);
+    let bundle = create_base_and_cached_build(
+        target_info,
+        default_bundle_directory,
+        &osdk_output_directory,
+        &cargo_target_directory,
+        &config,
+        ActionChoice::Run,
+        &["-C force-frame-pointers=yes"],
+    );
+
+    if let Err(exit_code) = bundle.try_run(&config, ActionChoice::Run) {
+        warn_msg!(
+            "The kernel exited with code {}. The samples may be incomplete.",
+            exit_code
+        );
+    }
+
+    // The kernel dumps the samples to the console, which is logged by QEMU.
+    let qemu_log_path = config.work_dir.join("qemu.log");
+    let mut qemu_log = String::new();
+    if File::open(&qemu_log_path)
+        .and_then(|mut file| file.read_to_string(&mut qemu_log))
+        .is_err()
+    {
+        exit_with_error!(
+            Errno::RunBundle,
+            "Failed to read the console log \"{}\". The QEMU arguments should log the console to it",
+            qemu_log_path.display()
+        );
+    }
+
+    let samples = qemu_log
+        .lines()
+        .filter_map(KernelSample::parse)
+        .collect::<Vec<_>>();
+    if samples.is_empty() {
+        exit_with_error!(
+            Errno::RunBundle,
+            "No profile samples are found in the console log \"{}\"",
+            qemu_log_path.display()
+        );
+    }
+    let profile = Profile::from_kernel_samples(&samples, &kernel_elf_path());
+
+    let out_args = &args.out_args;
+    let out_path = out_args.output_path(None);
+    println!(
+        "{} profile samples collected. Writing the output to \"{}\".",
+        profile.nr_stack_traces(),
+        out_path.display()
+    );
+
+    let out_file = File::create(out_path).expect("Failed to create output file");
+    profile.serialize_to(out_args.format(), out_args.cpu_mask, out_file);
+}
+
+/// A stack trace counted by the in-kernel sampling profiler.
+#[derive(Debug, PartialEq, Eq)]
+struct KernelSample {
+    cpu: u32,
+    count: usize,
+    /// The program counters starting from the interrupted instruction, or
+    /// `None` if the user mode is interrupted.
+    pcs: Option<Vec<u64>>,
+}
+
+impl KernelSample {
+    /// Parses a line in the form of
+    /// `[profile] cpu=<CPU> count=<COUNT> pcs=<PC>,<PC>,...`.
+    fn parse(line: &str) -> Option<Self> {
+        let fields = line.trim().strip_prefix("[profile] ")?;
+        let mut fields = fields.split_whitespace();
+        let cpu = fields.next()?.strip_prefix("cpu=")?.parse().ok()?;
+        let count = fields.next()?.strip_prefix("count=")?.parse().ok()?;
+        let pcs = match fields.next()?.strip_prefix("pcs=")? {
+            "user" => None,
+            pcs => Some(
+                pcs.split(',')
+                    .map(|pc| u64::from_str_radix(pc.strip_prefix("0x")?, 16).ok())
+                    .collect::<Option<Vec<_>>>()?,
+            ),
+        };
+        Some(Self { cpu, count, pcs })
+    }
+}
+
+/// Returns the function names of the program counters in the ELF file.
+fn symbolize(elf_path: &Path, pcs: &BTreeSet<u64>) -> HashMap<u64, String> {
+    let mut addr2line_proc = Command::new("addr2line")
+        .args(["-f", "-C", "-e"])
+        .arg(elf_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute addr2line");
+
+    let mut stdin = addr2line_proc.stdin.take().unwrap();
+    let input = pcs.iter().fold(String::new(), |mut input, pc| {
+        let _ = writeln!(input, "{:#x}", pc);
+        input
+    });
+    // Write in another thread so that a full output pipe does not block the input.
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let mut output = String::new();
+    addr2line_proc
+        .stdout
+        .take()
+        .unwrap()
+        .read_to_string(&mut output)
+        .expect("Failed to read the output of addr2line");
+    writer
+        .join()
+        .unwrap()
+        .expect("Failed to write to addr2line");
+    addr2line_proc.wait().unwrap();
+
+    // Each address is translated to a function name followed by a source location.
+    let impl_pattern = Regex::new(r"::\{.*?\}").unwrap();
+    pcs.iter()
+        .zip(output.lines().step_by(2))
+        .map(|(pc, func_name)| {
+            let func_name = ProfileBuffer::remove_generics(func_name);
+            (*pc, impl_pattern.replace_all(&func_name, "").to_string())
+        })
+        .collect()
+}
+
 #[derive(Debug, Default, Serialize, Deserialize)]
 struct Profile {
     // Index 0: capture; Index 1: CPU ID; Index 2: stack frame
@@ -215,6 +386,50 @@ impl Profile {
     fn nr_stack_traces(&self) -> usize {
         self.stack_traces.len()
     }
+
+    /// Builds the profile from the samples of the in-kernel sampling profiler.
+    ///
+    /// Each sample is a capture of a single CPU, so the counted stack traces
+    /// are repeated by their counts.
+    fn from_kernel_samples(samples: &[KernelSample], elf_path: &Path) -> Self {
+        // The return addresses point to the instructions after the calls, so
+        // they are moved back into the calls for symbolization.
+        let call_pcs = |pcs: &[u64]| {
+            pcs.iter()
+                .enumerate()
+                .map(|(i, pc)| if i == 0 { *pc } else { pc.saturating_sub(1) })
+                .collect::<Vec<_>>()
+        };
+
+        let all_pcs = samples
+            .iter()
+            .filter_map(|sample| sample.pcs.as_deref())
+            .flat_map(call_pcs)
+            .collect::<BTreeSet<_>>();
+        let func_names = symbolize(elf_path, &all_pcs);
+
+        let mut profile = Profile::default();
+        for sample in samples {
+            let stack = match &sample.pcs {
+                Some(pcs) => call_pcs(pcs)
+                    .iter()
+                    .map(|pc| {
+                        func_names
+                            .get(pc)
+                            .cloned()
+                            .unwrap_or_else(|| "??".to_owned())
+                    })
+                    .collect(),
+                None => vec!["[user]".to_owned()],
+            };
+            for _ in 0..sample.count {
+                profile
+                    .stack_traces
+                    .push(HashMap::from([(sample.cpu, stack.clone())]));
+            }
+        }
+        profile
+    }
 }
 
 #[derive(Debug)]
@@ -428,3 +643,26 @@ Thread 1 (Thread 1.1 (CPU#0 [running])):
     );
     assert_eq!(stack11[14], "??");
 }
+
+#[cfg(test)]
+#[test]
+fn test_kernel_sample_parse() {
+    assert_eq!(
+        KernelSample::parse("[profile] cpu=1 count=3 pcs=0xffffffff880b0f6f,0xffffffff8826b205\r"),
+        Some(KernelSample {
+            cpu: 1,
+            count: 3,
+            pcs: Some(vec![0xffffffff880b0f6f, 0xffffffff8826b205]),
+        })
+    );
+    assert_eq!(
+        KernelSample::parse("[profile] cpu=0 count=7 pcs=user"),
+        Some(KernelSample {
+            cpu: 0,
+            count: 7,
+            pcs: None,
+        })
+    );
+    assert_eq!(KernelSample::parse("[kernel] Spawn init thread"), None);
+    assert_eq!(KernelSample::parse("[profile] cpu=0 count=1 pcs=bad"), None);
+}
@@ -1,7 +1,5 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use std::process::exit;
-
 use vsc::VscLaunchConfig;
 
 use super::{
@@ -9,9 +7,12 @@ use super::{
     util::{is_tdx_enabled, DEFAULT_TARGET_RELPATH},
 };
 use crate::{
-    config::{scheme::ActionChoice, Config},
+    config::{
+        scheme::{ActionChoice, DEFAULT_GDB_SERVER_ADDR},
+        Config,
+    },
     error::Errno,
-    error_msg,
+    exit_with_error,
     util::{get_kernel_crate, get_target_directory},
     warn_msg,
 };
@@ -47,9 +48,25 @@ pub fn execute_run_command(config: &Config, gdb_server_args: Option<&str>) {
 fn adapt_for_gdb_server(config: &mut Config, gdb_server_str: &str) -> Option<VscLaunchConfig> {
     let gdb_server_args = GdbServerArgs::from_str(gdb_server_str);
 
+    enable_gdb_server(
+        config,
+        &gdb_server_args.host_addr,
+        gdb_server_args.wait_client,
+    );
+
+    gdb_server_args.vsc_launch_file.then(|| {
+        vsc::check_gdb_config(&gdb_server_args);
+        let profile = super::util::profile_name_adapter(&config.run.build.profile);
+        vsc::VscLaunchConfig::new(profile, &gdb_server_args.host_addr)
+    })
+}
+
+/// Lets QEMU run a GDB server on the address when running the kernel.
+///
+/// If `wait_client` is true, QEMU does not start the kernel until a GDB client attaches.
+pub(super) fn enable_gdb_server(config: &mut Config, gdb_stub_addr: &str, wait_client: bool) {
     // Add GDB server arguments to QEMU.
     let qemu_gdb_args = {
-        let gdb_stub_addr = gdb_server_args.host_addr.as_str();
         match gdb::stub_type_of(gdb_stub_addr) {
             gdb::StubAddrType::Unix => {
                 format!(
@@ -67,7 +84,7 @@ fn adapt_for_gdb_server(config: &mut Config, gdb_server_str: &str) -> Option<Vsc
     };
     config.run.qemu.args += &qemu_gdb_args;
 
-    if gdb_server_args.wait_client {
+    if wait_client {
         config.run.qemu.args += " -S";
     }
 
@@ -91,12 +108,6 @@ fn adapt_for_gdb_server(config: &mut Config, gdb_server_str: &str) -> Option<Vsc
             .override_configs
             .push(format!("profile.{}.debug=true", config.run.build.profile));
     }
-
-    gdb_server_args.vsc_launch_file.then(|| {
-        vsc::check_gdb_config(&gdb_server_args);
-        let profile = super::util::profile_name_adapter(&config.run.build.profile);
-        vsc::VscLaunchConfig::new(profile, &gdb_server_args.host_addr)
-    })
 }
 
 struct GdbServerArgs {
@@ -107,7 +118,7 @@ struct GdbServerArgs {
 
 impl GdbServerArgs {
     fn from_str(args: &str) -> Self {
-        let mut host_addr = ".osdk-gdb-socket".to_string();
+        let mut host_addr = DEFAULT_GDB_SERVER_ADDR.to_string();
         let mut wait_client = false;
         let mut vsc_launch_file = false;
 
@@ -118,8 +129,7 @@ impl GdbServerArgs {
                 ["wait-client"] => wait_client = true,
                 ["vscode"] => vsc_launch_file = true,
                 _ => {
-                    error_msg!("Invalid GDB server argument: {}", arg);
-                    exit(Errno::Cli as _);
+                    exit_with_error!(Errno::Cli, "Invalid GDB server argument: {}", arg);
                 }
             }
         }
@@ -132,7 +142,7 @@ impl GdbServerArgs {
     }
 }
 
-mod gdb {
+pub(super) mod gdb {
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum StubAddrType {
         Unix, // Unix Domain Socket
@@ -146,8 +156,7 @@ mod gdb {
     }
 
     pub mod tcp_addr_util {
-        use crate::{error::Errno, error_msg};
-        use std::process::exit;
+        use crate::{error::Errno, exit_with_error};
 
         fn strip_tcp_prefix(addr: &str) -> &str {
             addr.strip_prefix("tcp:").unwrap_or(addr)
@@ -156,8 +165,10 @@ mod gdb {
         fn parse_tcp_addr(addr: &str) -> (&str, u16) {
             let addr = strip_tcp_prefix(addr);
             if !addr.contains(':') {
-                error_msg!("Ambiguous GDB server address, use '[IP]:PORT' format");
-                exit(Errno::ParseMetadata as _);
+                exit_with_error!(
+                    Errno::ParseMetadata,
+                    "Ambiguous GDB server address, use '[IP]:PORT' format"
+                );
             }
             let mut iter = addr.split(':');
             let host = iter.next().unwrap();
@@ -265,20 +276,21 @@ mod vsc {
 
     /// Exit if the QEMU GDB server configuration is not valid
     pub fn check_gdb_config(args: &GdbServerArgs) {
-        use crate::{error::Errno, error_msg};
-        use std::process::exit;
+        use crate::{error::Errno, exit_with_error};
 
         // check GDB server address
         let gdb_stub_addr = args.host_addr.as_str();
         if gdb_stub_addr.is_empty() {
-            error_msg!("GDB server address is required to generate a VSCode launch file");
-            exit(Errno::ParseMetadata as _);
+            exit_with_error!(
+                Errno::ParseMetadata,
+                "GDB server address is required to generate a VSCode launch file"
+            );
         }
         if gdb::stub_type_of(gdb_stub_addr) != gdb::StubAddrType::Tcp {
-            error_msg!(
+            exit_with_error!(
+                Errno::ParseMetadata,
                 "Non-TCP GDB server address is not supported under '--gdb-server vscode' currently"
             );
-            exit(Errno::ParseMetadata as _);
         }
     }
 
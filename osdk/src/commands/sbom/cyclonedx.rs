@@ -0,0 +1,117 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Rendering the SBOM as a CycloneDX 1.5 document in JSON.
+//!
+//! See <https://cyclonedx.org/docs/1.5/json/> for the specification.
+
+use serde_json::{json, Map, Value};
+
+use super::{Component, ComponentKind, Sbom};
+
+/// The `bom-ref` of the image, on which all the components are dependencies.
+const IMAGE_BOM_REF: &str = "image";
+
+pub(super) fn render(sbom: &Sbom) -> Value {
+    let dependencies = sbom
+        .components
+        .iter()
+        .map(Component::bom_ref)
+        .collect::<Vec<_>>();
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", sbom.uuid()),
+        "version": 1,
+        "metadata": {
+            "timestamp": sbom.timestamp,
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": "cargo-osdk",
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+            },
+            "component": {
+                "type": "operating-system",
+                "bom-ref": IMAGE_BOM_REF,
+                "name": sbom.name,
+                "version": sbom.version,
+            },
+        },
+        "components": sbom.components.iter().map(render_component).collect::<Vec<_>>(),
+        "dependencies": [{
+            "ref": IMAGE_BOM_REF,
+            "dependsOn": dependencies,
+        }],
+    })
+}
+
+fn render_component(component: &Component) -> Value {
+    let kind = match component.kind {
+        ComponentKind::Crate | ComponentKind::GrubModule => "library",
+        ComponentKind::ImageFile => "file",
+        ComponentKind::Firmware => "firmware",
+    };
+
+    let mut object = Map::new();
+    object.insert("type".to_owned(), json!(kind));
+    object.insert("bom-ref".to_owned(), json!(component.bom_ref()));
+    object.insert("name".to_owned(), json!(component.name));
+    if let Some(version) = &component.version {
+        object.insert("version".to_owned(), json!(version));
+    }
+    if let Some(sha256) = &component.sha256 {
+        object.insert(
+            "hashes".to_owned(),
+            json!([{ "alg": "SHA-256", "content": sha256 }]),
+        );
+    }
+    if let Some(license) = &component.license {
+        object.insert("licenses".to_owned(), json!([{ "expression": license }]));
+    }
+    if let Some(purl) = &component.purl {
+        object.insert("purl".to_owned(), json!(purl));
+    }
+    if let Some(download_location) = &component.download_location {
+        object.insert(
+            "externalReferences".to_owned(),
+            json!([{ "type": "distribution", "url": download_location }]),
+        );
+    }
+    Value::Object(object)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{super::test::sample_sbom, *};
+
+    #[test]
+    fn render_cyclonedx() {
+        let sbom = sample_sbom();
+        let document = render(&sbom);
+
+        assert_eq!(document["bomFormat"], "CycloneDX");
+        assert_eq!(
+            document["serialNumber"],
+            format!("urn:uuid:{}", sbom.uuid())
+        );
+        assert_eq!(document["metadata"]["component"]["name"], "aster-nix");
+
+        let components = document["components"].as_array().unwrap();
+        assert_eq!(components.len(), sbom.components.len());
+        assert_eq!(components[0]["type"], "library");
+        assert_eq!(components[0]["purl"], "pkg:cargo/bitflags@1.3.2");
+        assert_eq!(
+            components[0]["licenses"][0]["expression"],
+            "MIT OR Apache-2.0"
+        );
+        assert_eq!(components[0]["hashes"][0]["alg"], "SHA-256");
+        assert_eq!(components[1]["type"], "firmware");
+        assert!(components[1].get("version").is_none());
+
+        let dependencies = &document["dependencies"][0];
+        assert_eq!(dependencies["ref"], IMAGE_BOM_REF);
+        assert_eq!(dependencies["dependsOn"][1], "firmware:OVMF.fd");
+    }
+}
@@ -0,0 +1,513 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Generating the software bill of materials (SBOM) of the built image.
+//!
+//! The SBOM lists the components that are baked into the image:
+//!  - the Rust crates that are linked into the kernel, including the workspace crates;
+//!  - the files of the image, i.e., the kernel, the initramfs, and the VM image;
+//!  - the firmware specified in the QEMU arguments (e.g., OVMF);
+//!  - the GRUB modules if the image is booted with GRUB.
+//!
+//! The components are identified by their names and versions, and by the SHA-256 digests
+//! if the contents are known.
+
+mod cyclonedx;
+pub(super) mod sha256;
+mod spdx;
+
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use self::sha256::{sha256_file_hex, Sha256};
+use super::{
+    build::create_base_and_cached_build,
+    util::{cargo, to_hex, DEFAULT_TARGET_RELPATH},
+};
+use crate::{
+    arch::Arch,
+    base_crate::{new_base_crate, BaseCrateType},
+    bundle::{file::BundleFile, Bundle},
+    cli::{SbomArgs, SbomFormat},
+    config::{
+        scheme::{firmware_files, ActionChoice, BootMethod},
+        Config,
+    },
+    error::Errno,
+    exit_with_error,
+    util::{get_kernel_crate, get_target_directory},
+    warn_msg,
+};
+
+/// The license of EDK II, from which OVMF is built.
+const OVMF_LICENSE: &str = "BSD-2-Clause-Patent";
+const GRUB_LICENSE: &str = "GPL-3.0-or-later";
+
+pub fn execute_sbom_command(config: &Config, args: &SbomArgs) {
+    let cargo_target_directory = get_target_directory();
+    let osdk_output_directory = cargo_target_directory.join(DEFAULT_TARGET_RELPATH);
+    let target_info = get_kernel_crate();
+    let bundle_path = osdk_output_directory.join(&target_info.name);
+    let bundle = create_base_and_cached_build(
+        target_info.clone(),
+        &bundle_path,
+        &osdk_output_directory,
+        &cargo_target_directory,
+        config,
+        ActionChoice::Run,
+        &[],
+    );
+    // The base crate has just been built, so it is reused instead of being created again.
+    let base_crate_path = new_base_crate(
+        BaseCrateType::Run,
+        osdk_output_directory.join(&target_info.name),
+        &target_info.name,
+        &target_info.path,
+        false,
+    );
+
+    let mut components = image_components(&bundle);
+    components.extend(firmware_components(&config.run.qemu.args));
+    if matches!(
+        config.run.boot.method,
+        BootMethod::GrubRescueIso | BootMethod::GrubQcow2
+    ) {
+        components.extend(grub_components(&config.run.grub.grub_mkrescue));
+    }
+    components.extend(crate_components(&base_crate_path, config.target_arch));
+
+    let sbom = Sbom {
+        name: target_info.name.clone(),
+        version: target_info.version.clone(),
+        timestamp: timestamp(),
+        components,
+    };
+    let (document, extension) = match args.format {
+        SbomFormat::Cyclonedx => (cyclonedx::render(&sbom), "cdx.json"),
+        SbomFormat::Spdx => (spdx::render(&sbom), "spdx.json"),
+    };
+
+    let output = args.output.clone().unwrap_or_else(|| {
+        osdk_output_directory.join(format!("{}.{}", target_info.name, extension))
+    });
+    let document = serde_json::to_string_pretty(&document).unwrap() + "\n";
+    fs::write(&output, document).unwrap_or_else(|err| {
+        exit_with_error!(
+            Errno::Cli,
+            "Cannot write the SBOM to {}: {}",
+            output.display(),
+            err
+        )
+    });
+    println!(
+        "Generated the SBOM with {} components at {}",
+        sbom.components.len(),
+        output.display()
+    );
+}
+
+/// The software bill of materials of an image.
+struct Sbom {
+    /// The name of the image, i.e., the name of the kernel crate.
+    name: String,
+    /// The version of the image, i.e., the version of the kernel crate.
+    version: String,
+    /// The creation time in RFC 3339.
+    timestamp: String,
+    components: Vec<Component>,
+}
+
+impl Sbom {
+    /// Returns a UUID that identifies the SBOM.
+    ///
+    /// The UUID is derived from the contents, so the same image results in the same SBOM if
+    /// `SOURCE_DATE_EPOCH` is set. It is a version 8 UUID as specified by RFC 9562.
+    fn uuid(&self) -> String {
+        let mut hasher = Sha256::new();
+        for field in [&self.name, &self.version, &self.timestamp] {
+            hasher.update(field.as_bytes());
+            hasher.update(&[0]);
+        }
+        for component in self.components.iter() {
+            hasher.update(component.bom_ref().as_bytes());
+            hasher.update(component.sha256.as_deref().unwrap_or_default().as_bytes());
+            hasher.update(&[0]);
+        }
+        let mut bytes = hasher.finish();
+        bytes[6] = (bytes[6] & 0x0f) | 0x80;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        let hex = to_hex(&bytes[..16]);
+        format!(
+            "{}-{}-{}-{}-{}",
+            &hex[0..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..32]
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ComponentKind {
+    /// A Rust crate linked into the kernel.
+    Crate,
+    /// A file of the image.
+    ImageFile,
+    /// A firmware that boots the image, e.g., OVMF.
+    Firmware,
+    /// A GRUB module in the image.
+    GrubModule,
+}
+
+#[derive(Debug, Clone)]
+struct Component {
+    kind: ComponentKind,
+    name: String,
+    version: Option<String>,
+    /// The SPDX license expression, if known.
+    license: Option<String>,
+    /// The package URL (see <https://github.com/package-url/purl-spec>), if any.
+    purl: Option<String>,
+    /// Where the component can be downloaded, if known.
+    download_location: Option<String>,
+    /// The SHA-256 digest in lowercase hexadecimal, if known.
+    sha256: Option<String>,
+}
+
+impl Component {
+    fn new(kind: ComponentKind, name: String) -> Self {
+        Self {
+            kind,
+            name,
+            version: None,
+            license: None,
+            purl: None,
+            download_location: None,
+            sha256: None,
+        }
+    }
+
+    /// Returns a string that identifies the component in the SBOM.
+    fn bom_ref(&self) -> String {
+        if let Some(purl) = &self.purl {
+            return purl.clone();
+        }
+        let kind = match self.kind {
+            ComponentKind::Crate => "crate",
+            ComponentKind::ImageFile => "image",
+            ComponentKind::Firmware => "firmware",
+            ComponentKind::GrubModule => "grub",
+        };
+        match &self.version {
+            Some(version) => format!("{}:{}@{}", kind, self.name, version),
+            None => format!("{}:{}", kind, self.name),
+        }
+    }
+}
+
+/// Returns the files of the built image.
+fn image_components(bundle: &Bundle) -> Vec<Component> {
+    let aster_bin = bundle.aster_bin().map(|aster_bin| aster_bin.path().clone());
+    [aster_bin, bundle.initramfs_path(), bundle.vm_image_path()]
+        .into_iter()
+        .flatten()
+        .map(|path| {
+            let mut component = Component::new(ComponentKind::ImageFile, file_name(&path));
+            component.sha256 = Some(hash_file(&path));
+            component
+        })
+        .collect()
+}
+
+/// Returns the firmware files that are specified in the QEMU arguments.
+fn firmware_components(qemu_args: &str) -> Vec<Component> {
+    firmware_files(qemu_args)
+        .into_iter()
+        .map(PathBuf::from)
+        .filter(|path| {
+            let is_found = path.is_file();
+            if !is_found {
+                warn_msg!(
+                    "The firmware `{}` is not found, so it is not included in the SBOM",
+                    path.display()
+                );
+            }
+            is_found
+        })
+        .map(|path| {
+            let name = file_name(&path);
+            let mut component = Component::new(ComponentKind::Firmware, name.clone());
+            // The firmware files of OVMF are usually named after it, e.g., `OVMF_CODE.fd`.
+            if name.to_ascii_uppercase().contains("OVMF") {
+                component.license = Some(OVMF_LICENSE.to_owned());
+            }
+            component.sha256 = Some(hash_file(&path));
+            component
+        })
+        .collect()
+}
+
+/// Returns the GRUB modules that `grub-mkrescue` puts into the image.
+///
+/// `grub-mkrescue` includes the modules of all the platforms that are installed, which are
+/// found in the `lib/grub` directory of the GRUB installation.
+fn grub_components(grub_mkrescue: &Path) -> Vec<Component> {
+    let Some(lib_dir) = find_executable(grub_mkrescue)
+        .and_then(|path| fs::canonicalize(path).ok())
+        .and_then(|path| Some(path.parent()?.parent()?.join("lib").join("grub")))
+        .filter(|lib_dir| lib_dir.is_dir())
+    else {
+        warn_msg!(
+            "Cannot find the GRUB modules of `{}`, so they are not included in the SBOM",
+            grub_mkrescue.display()
+        );
+        return Vec::new();
+    };
+    let version = grub_version(grub_mkrescue);
+
+    let mut platform_dirs = fs::read_dir(&lib_dir)
+        .unwrap()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|dir| dir.join("moddep.lst").is_file())
+        .collect::<Vec<_>>();
+    platform_dirs.sort();
+
+    let mut components = Vec::new();
+    for platform_dir in platform_dirs {
+        let mut modules = fs::read_dir(&platform_dir)
+            .unwrap()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "mod"))
+            .collect::<Vec<_>>();
+        modules.sort();
+
+        for module in modules {
+            let name = format!("grub/{}/{}", file_name(&platform_dir), file_name(&module));
+            let mut component = Component::new(ComponentKind::GrubModule, name);
+            component.version.clone_from(&version);
+            component.license = Some(GRUB_LICENSE.to_owned());
+            component.sha256 = Some(hash_file(&module));
+            components.push(component);
+        }
+    }
+    components
+}
+
+/// Returns the version of GRUB, e.g., `2.12` from `grub-mkrescue (GRUB) 2.12`.
+fn grub_version(grub_mkrescue: &Path) -> Option<String> {
+    let output = Command::new(grub_mkrescue).arg("--version").output().ok()?;
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    stdout.split_whitespace().last().map(str::to_owned)
+}
+
+/// Returns the crates that are built for the target architecture.
+///
+/// The crates are resolved from the base crate, whose `Cargo.lock` provides the checksums of
+/// the crates from registries.
+fn crate_components(base_crate_path: &Path, arch: Arch) -> Vec<Component> {
+    let mut command = cargo();
+    command
+        .args(["metadata", "--format-version", "1", "--filter-platform"])
+        .arg(arch.triple())
+        .current_dir(base_crate_path);
+    let output = command.output().unwrap();
+    if !output.status.success() {
+        exit_with_error!(
+            Errno::GetMetadata,
+            "Cannot get the metadata of the crates: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let metadata: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    let checksums = fs::read_to_string(base_crate_path.join("Cargo.lock"))
+        .map(|lock| lock_checksums(&lock))
+        .unwrap_or_default();
+
+    let root = metadata["resolve"]["root"].as_str();
+    let mut components = metadata["packages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|package| package["id"].as_str() != root)
+        .map(|package| {
+            let name = package["name"].as_str().unwrap().to_owned();
+            let version = package["version"].as_str().unwrap().to_owned();
+            let source = package["source"].as_str();
+
+            let mut component = Component::new(ComponentKind::Crate, name.clone());
+            component.license = package["license"].as_str().map(spdx_license);
+            match source {
+                Some(source) if source.starts_with("registry+") => {
+                    component.purl = Some(format!("pkg:cargo/{}@{}", name, version));
+                    if source.ends_with("github.com/rust-lang/crates.io-index") {
+                        component.download_location = Some(format!(
+                            "https://crates.io/api/v1/crates/{}/{}/download",
+                            name, version
+                        ));
+                    }
+                }
+                Some(source) => {
+                    component.download_location = source.strip_prefix("git+").map(str::to_owned);
+                }
+                // A crate from a path, e.g., a workspace crate.
+                None => {}
+            }
+            component.sha256 = checksums.get(&(name, version.clone())).cloned();
+            component.version = Some(version);
+            component
+        })
+        .collect::<Vec<_>>();
+    components.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    components
+}
+
+/// Converts the license of a crate to an SPDX license expression.
+///
+/// Cargo accepts the deprecated `/` as the separator of alternative licenses, e.g.,
+/// `MIT/Apache-2.0`, which is `MIT OR Apache-2.0` in SPDX.
+fn spdx_license(license: &str) -> String {
+    license
+        .split('/')
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+/// Returns the checksums of the packages in `Cargo.lock`, indexed by the names and versions.
+///
+/// The checksums are the SHA-256 digests of the `.crate` files.
+fn lock_checksums(lock: &str) -> HashMap<(String, String), String> {
+    let Ok(lock) = toml::from_str::<toml::Value>(lock) else {
+        return HashMap::new();
+    };
+    let Some(packages) = lock.get("package").and_then(|packages| packages.as_array()) else {
+        return HashMap::new();
+    };
+
+    packages
+        .iter()
+        .filter_map(|package| {
+            let name = package.get("name")?.as_str()?;
+            let version = package.get("version")?.as_str()?;
+            let checksum = package.get("checksum")?.as_str()?;
+            Some(((name.to_owned(), version.to_owned()), checksum.to_owned()))
+        })
+        .collect()
+}
+
+/// Returns the creation time of the SBOM in RFC 3339.
+///
+/// Like the other reproducible artifacts, `SOURCE_DATE_EPOCH` is used if it is set. See
+/// <https://reproducible-builds.org/specs/source-date-epoch/>.
+pub(super) fn timestamp() -> String {
+    let time = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<i64>().ok())
+        .and_then(|epoch| chrono::DateTime::from_timestamp(epoch, 0))
+        .unwrap_or_else(chrono::Utc::now);
+    time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+/// Returns the path of the program, which is either a path or a name in `PATH`.
+fn find_executable(program: &Path) -> Option<PathBuf> {
+    if program.components().count() > 1 {
+        return program.is_file().then(|| program.to_owned());
+    }
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+fn hash_file(path: &Path) -> String {
+    sha256_file_hex(path).unwrap_or_else(|err| {
+        exit_with_error!(
+            Errno::GetMetadata,
+            "Cannot read {} for the SBOM: {}",
+            path.display(),
+            err
+        )
+    })
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap().to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    pub(super) fn sample_sbom() -> Sbom {
+        let mut bitflags = Component::new(ComponentKind::Crate, "bitflags".to_owned());
+        bitflags.version = Some("1.3.2".to_owned());
+        bitflags.license = Some(spdx_license("MIT/Apache-2.0"));
+        bitflags.purl = Some("pkg:cargo/bitflags@1.3.2".to_owned());
+        bitflags.sha256 =
+            Some("bef38d45163c2f1dde094a7dfd33ccf595c92905c8f8f4fdc18d06fb1037718a".to_owned());
+
+        let mut ovmf = Component::new(ComponentKind::Firmware, "OVMF.fd".to_owned());
+        ovmf.license = Some(OVMF_LICENSE.to_owned());
+        ovmf.sha256 =
+            Some("5e2b6e5ce4fd4bb4ba7a7e8e1f0e7e4c0b8f8a3a1cfcfd12e8b5f7c7d2bb0e61".to_owned());
+
+        Sbom {
+            name: "aster-nix".to_owned(),
+            version: "0.15.0".to_owned(),
+            timestamp: "2025-01-01T00:00:00Z".to_owned(),
+            components: vec![bitflags, ovmf],
+        }
+    }
+
+    #[test]
+    fn stable_uuid() {
+        let sbom = sample_sbom();
+        let uuid = sbom.uuid();
+        assert_eq!(uuid, sample_sbom().uuid());
+        assert_eq!(uuid.len(), 36);
+        // The version is 8 and the variant is RFC 9562.
+        assert_eq!(&uuid[14..15], "8");
+        assert!(matches!(&uuid[19..20], "8" | "9" | "a" | "b"));
+
+        let mut other = sample_sbom();
+        other.components[1].sha256 = None;
+        assert_ne!(uuid, other.uuid());
+    }
+
+    #[test]
+    fn convert_licenses() {
+        assert_eq!(spdx_license("MIT"), "MIT");
+        assert_eq!(spdx_license("MIT/Apache-2.0"), "MIT OR Apache-2.0");
+        assert_eq!(spdx_license("MIT OR Apache-2.0"), "MIT OR Apache-2.0");
+    }
+
+    #[test]
+    fn parse_lock_checksums() {
+        let lock = r#"
+version = 4
+
+[[package]]
+name = "bitflags"
+version = "1.3.2"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "bef38d45163c2f1dde094a7dfd33ccf595c92905c8f8f4fdc18d06fb1037718a"
+
+[[package]]
+name = "ostd"
+version = "0.15.0"
+"#;
+        let checksums = lock_checksums(lock);
+        assert_eq!(checksums.len(), 1);
+        assert_eq!(
+            checksums[&("bitflags".to_owned(), "1.3.2".to_owned())],
+            "bef38d45163c2f1dde094a7dfd33ccf595c92905c8f8f4fdc18d06fb1037718a"
+        );
+    }
+}
@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Rendering the SBOM as an SPDX 2.3 document in JSON.
+//!
+//! See <https://spdx.github.io/spdx-spec/v2.3/> for the specification.
+
+use serde_json::{json, Map, Value};
+
+use super::{Component, ComponentKind, Sbom};
+
+const DOCUMENT_SPDX_ID: &str = "SPDXRef-DOCUMENT";
+const IMAGE_SPDX_ID: &str = "SPDXRef-Image";
+const NOASSERTION: &str = "NOASSERTION";
+
+pub(super) fn render(sbom: &Sbom) -> Value {
+    let spdx_ids = sbom
+        .components
+        .iter()
+        .enumerate()
+        .map(|(index, component)| spdx_id(index, component))
+        .collect::<Vec<_>>();
+
+    let mut packages = vec![json!({
+        "name": sbom.name,
+        "SPDXID": IMAGE_SPDX_ID,
+        "versionInfo": sbom.version,
+        "downloadLocation": NOASSERTION,
+        "filesAnalyzed": false,
+        "primaryPackagePurpose": "OPERATING-SYSTEM",
+    })];
+    packages.extend(
+        sbom.components
+            .iter()
+            .zip(spdx_ids.iter())
+            .map(|(component, spdx_id)| render_package(component, spdx_id)),
+    );
+
+    let mut relationships = vec![json!({
+        "spdxElementId": DOCUMENT_SPDX_ID,
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": IMAGE_SPDX_ID,
+    })];
+    relationships.extend(spdx_ids.iter().map(|spdx_id| {
+        json!({
+            "spdxElementId": IMAGE_SPDX_ID,
+            "relationshipType": "CONTAINS",
+            "relatedSpdxElement": spdx_id,
+        })
+    }));
+
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": DOCUMENT_SPDX_ID,
+        "name": format!("{}-{}", sbom.name, sbom.version),
+        "documentNamespace": format!(
+            "https://spdx.org/spdxdocs/{}-{}-{}",
+            sbom.name,
+            sbom.version,
+            sbom.uuid()
+        ),
+        "creationInfo": {
+            "created": sbom.timestamp,
+            "creators": [format!("Tool: cargo-osdk-{}", env!("CARGO_PKG_VERSION"))],
+        },
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+fn render_package(component: &Component, spdx_id: &str) -> Value {
+    let purpose = match component.kind {
+        ComponentKind::Crate | ComponentKind::GrubModule => "LIBRARY",
+        ComponentKind::ImageFile => "FILE",
+        ComponentKind::Firmware => "FIRMWARE",
+    };
+
+    let mut object = Map::new();
+    object.insert("name".to_owned(), json!(component.name));
+    object.insert("SPDXID".to_owned(), json!(spdx_id));
+    if let Some(version) = &component.version {
+        object.insert("versionInfo".to_owned(), json!(version));
+    }
+    object.insert(
+        "downloadLocation".to_owned(),
+        json!(component
+            .download_location
+            .as_deref()
+            .unwrap_or(NOASSERTION)),
+    );
+    object.insert("filesAnalyzed".to_owned(), json!(false));
+    object.insert("licenseConcluded".to_owned(), json!(NOASSERTION));
+    object.insert(
+        "licenseDeclared".to_owned(),
+        json!(component.license.as_deref().unwrap_or(NOASSERTION)),
+    );
+    object.insert("copyrightText".to_owned(), json!(NOASSERTION));
+    if let Some(sha256) = &component.sha256 {
+        object.insert(
+            "checksums".to_owned(),
+            json!([{ "algorithm": "SHA256", "checksumValue": sha256 }]),
+        );
+    }
+    if let Some(purl) = &component.purl {
+        object.insert(
+            "externalRefs".to_owned(),
+            json!([{
+                "referenceCategory": "PACKAGE-MANAGER",
+                "referenceType": "purl",
+                "referenceLocator": purl,
+            }]),
+        );
+    }
+    object.insert("primaryPackagePurpose".to_owned(), json!(purpose));
+    Value::Object(object)
+}
+
+/// Returns the SPDX identifier of the component.
+///
+/// The identifier consists of letters, numbers, `.`, and `-`. The index makes it unique.
+fn spdx_id(index: usize, component: &Component) -> String {
+    let name = component
+        .name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+    format!("SPDXRef-{}-{}", index, name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{super::test::sample_sbom, *};
+
+    #[test]
+    fn render_spdx() {
+        let sbom = sample_sbom();
+        let document = render(&sbom);
+
+        assert_eq!(document["spdxVersion"], "SPDX-2.3");
+        assert!(document["documentNamespace"]
+            .as_str()
+            .unwrap()
+            .ends_with(&sbom.uuid()));
+
+        let packages = document["packages"].as_array().unwrap();
+        assert_eq!(packages.len(), sbom.components.len() + 1);
+        assert_eq!(packages[0]["SPDXID"], IMAGE_SPDX_ID);
+        assert_eq!(packages[1]["SPDXID"], "SPDXRef-0-bitflags");
+        assert_eq!(packages[1]["licenseDeclared"], "MIT OR Apache-2.0");
+        assert_eq!(
+            packages[1]["externalRefs"][0]["referenceLocator"],
+            "pkg:cargo/bitflags@1.3.2"
+        );
+        assert_eq!(packages[2]["SPDXID"], "SPDXRef-1-OVMF.fd");
+        assert_eq!(packages[2]["downloadLocation"], NOASSERTION);
+        assert_eq!(packages[2]["checksums"][0]["algorithm"], "SHA256");
+
+        let relationships = document["relationships"].as_array().unwrap();
+        assert_eq!(relationships.len(), sbom.components.len() + 1);
+        assert_eq!(relationships[0]["relationshipType"], "DESCRIBES");
+        assert_eq!(relationships[2]["relatedSpdxElement"], "SPDXRef-1-OVMF.fd");
+    }
+}
@@ -0,0 +1,693 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Running the scenarios that check the user-visible behavior of the kernel.
+//!
+//! A scenario is described by a TOML file, e.g.,
+//!
+//! ```toml
+//! # The command run by the init process, which replaces the configured init arguments.
+//! init_args = ["sh", "-c", "echo hello"]
+//! # The expected exit code of OSDK when running the kernel, i.e., 0 if the init
+//! # process exits successfully and 1 if it fails.
+//! exit_code = 0
+//! # The patterns that must match some lines of the output.
+//! expect = ["^hello$"]
+//! # The output before the first line matching the pattern is not compared.
+//! start_after = "^Boot completed$"
+//! # The lines matching these patterns are not compared.
+//! ignore = ["^\\[ *[0-9.]+\\]"]
+//! ```
+//!
+//! The output of the kernel is compared with the golden file, which is the file
+//! with the same name but the `golden` extension by default. The golden file
+//! can be updated with the observed output with `--bless`.
+//!
+//! A scenario can also consist of steps, which are executed in order by the
+//! guest agent (see [`super::agent`]) without rebooting the kernel, e.g.,
+//!
+//! ```toml
+//! init_args = ["/test/osdk_agent/osdk_agent"]
+//!
+//! [[steps]]
+//! put = "input.txt"
+//! to = "/tmp/input.txt"
+//!
+//! [[steps]]
+//! run = ["sort", "-o", "/tmp/output.txt", "/tmp/input.txt"]
+//!
+//! [[steps]]
+//! get = "/tmp/output.txt"
+//! to = "output.txt"
+//! ```
+//!
+//! In this case, the output that is compared with the golden file is the
+//! transcript of the commands instead of the output of the kernel.
+
+use std::{
+    fs,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, Stdio},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+
+use regex::Regex;
+
+use super::{
+    agent::AgentSession,
+    build::create_base_and_cached_build,
+    test::apply_kcmd_args,
+    util::{decode_output, strip_ansi_escapes, DEFAULT_TARGET_RELPATH},
+};
+use crate::{
+    bundle::{kernel_exit_result, panic_report::PanicReport, qemu_install_help, Bundle},
+    config::{scheme::ActionChoice, Config},
+    diagnostic::exit_on_launch_failure,
+    error::Errno,
+    error_msg, exit_with_error,
+    util::{get_kernel_crate, get_target_directory},
+};
+
+pub fn execute_scenarios(config: &Config, paths: &[PathBuf], bless: bool) {
+    let scenarios: Vec<_> = paths.iter().map(|path| Scenario::load(path)).collect();
+
+    let mut failures = Vec::new();
+    for scenario in scenarios.iter() {
+        let result = run_scenario(config, scenario, bless);
+        match &result {
+            Ok(()) => println!("scenario {} ... ok", scenario.name),
+            Err(_) => println!("scenario {} ... FAILED", scenario.name),
+        }
+        if let Err(message) = result {
+            failures.push((&scenario.name, message));
+        }
+    }
+
+    println!(
+        "\nscenario summary: {} passed; {} failed",
+        scenarios.len() - failures.len(),
+        failures.len()
+    );
+    if failures.is_empty() {
+        return;
+    }
+    for (name, message) in failures.iter() {
+        error_msg!("Failed {}:\n{}", name, message);
+    }
+    std::process::exit(1);
+}
+
+/// A scenario loaded from the TOML file.
+struct Scenario {
+    name: String,
+    init_args: Vec<String>,
+    kcmd_args: Vec<String>,
+    exit_code: i32,
+    expect: Vec<Regex>,
+    start_after: Option<Regex>,
+    ignore: Vec<Regex>,
+    timeout: Option<Duration>,
+    golden: PathBuf,
+    steps: Vec<Step>,
+}
+
+/// A step of the scenario, which is executed by the guest agent.
+#[derive(Debug, PartialEq, Eq)]
+enum Step {
+    /// Runs the command in the guest.
+    Run(Vec<String>),
+    /// Copies the file from the host to the guest.
+    Put {
+        host: PathBuf,
+        guest: String,
+        mode: u32,
+    },
+    /// Copies the file from the guest to the host.
+    Get { guest: String, host: PathBuf },
+}
+
+/// The content of a scenario file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScenarioFile {
+    init_args: Vec<String>,
+    #[serde(default)]
+    kcmd_args: Vec<String>,
+    #[serde(default)]
+    exit_code: i32,
+    #[serde(default)]
+    expect: Vec<String>,
+    start_after: Option<String>,
+    #[serde(default)]
+    ignore: Vec<String>,
+    /// The time limit in seconds.
+    timeout: Option<u64>,
+    /// The path of the golden file, relative to the scenario file.
+    golden: Option<PathBuf>,
+    #[serde(default)]
+    steps: Vec<StepFile>,
+}
+
+/// The content of a step in a scenario file.
+///
+/// Exactly one of `run`, `put`, and `get` must be specified.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StepFile {
+    run: Option<Vec<String>>,
+    /// The host file to copy, relative to the scenario file.
+    put: Option<PathBuf>,
+    /// The guest file to copy.
+    get: Option<String>,
+    /// The destination of the copy.
+    to: Option<String>,
+    /// The permission bits of the file copied to the guest.
+    mode: Option<u32>,
+}
+
+impl Scenario {
+    fn load(path: &Path) -> Self {
+        let content = fs::read_to_string(path).unwrap_or_else(|err| {
+            exit_with_error!(
+                Errno::GetMetadata,
+                "Cannot read the scenario {}: {}",
+                path.display(),
+                err
+            );
+        });
+        let file: ScenarioFile = toml::from_str(&content).unwrap_or_else(|err| {
+            exit_with_error!(
+                Errno::ParseMetadata,
+                "Cannot parse the scenario {}: {}",
+                path.display(),
+                err
+            );
+        });
+        Self::from_file(path, file)
+    }
+
+    fn from_file(path: &Path, file: ScenarioFile) -> Self {
+        let compile = |pattern: &str| {
+            Regex::new(pattern).unwrap_or_else(|err| {
+                exit_with_error!(
+                    Errno::ParseMetadata,
+                    "Invalid pattern in the scenario {}: {}",
+                    path.display(),
+                    err
+                );
+            })
+        };
+
+        // The paths are resolved before OSDK changes the current directory to build the kernel.
+        let path = std::env::current_dir().unwrap().join(path);
+        let golden = match file.golden {
+            Some(golden) => path.parent().unwrap().join(golden),
+            None => path.with_extension("golden"),
+        };
+
+        let steps = file
+            .steps
+            .into_iter()
+            .map(|step| {
+                Step::from_file(&path, step).unwrap_or_else(|message| {
+                    exit_with_error!(
+                        Errno::ParseMetadata,
+                        "Invalid step in the scenario {}: {}",
+                        path.display(),
+                        message
+                    );
+                })
+            })
+            .collect();
+
+        Self {
+            name: path.file_stem().unwrap().to_string_lossy().into_owned(),
+            init_args: file.init_args,
+            kcmd_args: file.kcmd_args,
+            exit_code: file.exit_code,
+            expect: file.expect.iter().map(|pattern| compile(pattern)).collect(),
+            start_after: file.start_after.as_deref().map(compile),
+            ignore: file.ignore.iter().map(|pattern| compile(pattern)).collect(),
+            timeout: file.timeout.map(Duration::from_secs),
+            golden,
+            steps,
+        }
+    }
+
+    /// Applies the kernel command line arguments and the init arguments of the scenario.
+    fn apply_to(&self, kcmdline: &mut Vec<String>) {
+        let separator = kcmdline
+            .iter()
+            .position(|arg| arg == "--")
+            .unwrap_or(kcmdline.len());
+        kcmdline.truncate(separator);
+        kcmdline.push("--".to_owned());
+        kcmdline.extend(self.init_args.iter().cloned());
+        apply_kcmd_args(kcmdline, &self.kcmd_args);
+    }
+
+    /// Returns the lines of the output that are compared with the golden file.
+    fn filter_output(&self, output: &str) -> Vec<String> {
+        let mut lines = output
+            .lines()
+            .map(|line| strip_ansi_escapes(line).trim_end().to_owned());
+        if let Some(start_after) = &self.start_after {
+            lines.by_ref().find(|line| start_after.is_match(line));
+        }
+        lines
+            .filter(|line| !self.ignore.iter().any(|pattern| pattern.is_match(line)))
+            .collect()
+    }
+
+    /// Executes the steps with the guest agent and returns the transcript.
+    ///
+    /// The transcript consists of the command lines prefixed with `$ `, each
+    /// followed by the standard output and then the standard error of the
+    /// command, as well as the exit code if it is not zero.
+    fn run_steps<W: Write>(&self, session: &mut AgentSession<W>) -> Result<String, String> {
+        let mut transcript = String::new();
+        for step in self.steps.iter() {
+            match step {
+                Step::Run(argv) => {
+                    let output = session.run(argv)?;
+                    transcript.push_str(&format!("$ {}\n", argv.join(" ")));
+                    transcript.push_str(&String::from_utf8_lossy(&output.stdout));
+                    transcript.push_str(&String::from_utf8_lossy(&output.stderr));
+                    if !transcript.ends_with('\n') {
+                        transcript.push('\n');
+                    }
+                    if output.exit_code != 0 {
+                        transcript.push_str(&format!("[exit code {}]\n", output.exit_code));
+                    }
+                }
+                Step::Put { host, guest, mode } => {
+                    let data = fs::read(host)
+                        .map_err(|err| format!("cannot read {}: {}", host.display(), err))?;
+                    session.put(guest, &data, *mode)?;
+                }
+                Step::Get { guest, host } => {
+                    let data = session.get(guest)?;
+                    fs::write(host, data)
+                        .map_err(|err| format!("cannot write {}: {}", host.display(), err))?;
+                }
+            }
+        }
+        Ok(transcript)
+    }
+
+    /// Checks the observed output and exit code of the kernel.
+    ///
+    /// If `bless` is true, the golden file is updated instead of being compared.
+    fn check(&self, output: &str, exit_code: i32, bless: bool) -> Result<(), String> {
+        let mut problems = Vec::new();
+
+        if exit_code != self.exit_code {
+            problems.push(format!(
+                "expected the exit code {}, but got {}",
+                self.exit_code, exit_code
+            ));
+        }
+
+        let lines = self.filter_output(output);
+        for pattern in self.expect.iter() {
+            if !lines.iter().any(|line| pattern.is_match(line)) {
+                problems.push(format!("no line matches the pattern `{}`", pattern));
+            }
+        }
+
+        if bless {
+            let mut content = lines.join("\n");
+            content.push('\n');
+            if let Err(err) = fs::write(&self.golden, content) {
+                problems.push(format!(
+                    "cannot write the golden file {}: {}",
+                    self.golden.display(),
+                    err
+                ));
+            }
+        } else {
+            match fs::read_to_string(&self.golden) {
+                Ok(golden) => {
+                    let golden: Vec<_> = golden.lines().map(str::to_owned).collect();
+                    if let Some(diff) = diff_lines(&golden, &lines) {
+                        problems.push(format!(
+                            "the output differs from the golden file {}:\n{}",
+                            self.golden.display(),
+                            diff
+                        ));
+                    }
+                }
+                Err(err) => problems.push(format!(
+                    "cannot read the golden file {} ({}), run with `--bless` to create it",
+                    self.golden.display(),
+                    err
+                )),
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("\n"))
+        }
+    }
+}
+
+impl Step {
+    /// Resolves the step, where `path` is the absolute path of the scenario file.
+    fn from_file(path: &Path, file: StepFile) -> Result<Self, String> {
+        let base = path.parent().unwrap();
+        match (file.run, file.put, file.get, file.to) {
+            (Some(argv), None, None, None) if file.mode.is_none() => {
+                if argv.is_empty() {
+                    return Err("the command to run is empty".to_owned());
+                }
+                Ok(Self::Run(argv))
+            }
+            (None, Some(host), None, Some(guest)) => Ok(Self::Put {
+                host: base.join(host),
+                guest,
+                mode: file.mode.unwrap_or(0o644),
+            }),
+            (None, None, Some(guest), Some(host)) if file.mode.is_none() => Ok(Self::Get {
+                guest,
+                host: base.join(host),
+            }),
+            (None, None, None, _) => Err("one of `run`, `put`, and `get` is required".to_owned()),
+            _ => Err(
+                "only one of `run`, `put`, and `get` is allowed, `to` is required by \
+                 `put` and `get`, and `mode` is only allowed by `put`"
+                    .to_owned(),
+            ),
+        }
+    }
+}
+
+/// Builds and boots the kernel for the scenario and checks the outcome.
+fn run_scenario(config: &Config, scenario: &Scenario, bless: bool) -> Result<(), String> {
+    let cargo_target_directory = get_target_directory();
+    let osdk_output_directory = cargo_target_directory.join(DEFAULT_TARGET_RELPATH);
+
+    let mut config = config.clone();
+    scenario.apply_to(&mut config.run.boot.kcmdline);
+
+    // The bundle is rebuilt only if the boot method bakes the kernel command line into the image.
+    let target_info = get_kernel_crate();
+    let default_bundle_directory = osdk_output_directory.join(&target_info.name);
+    let bundle = create_base_and_cached_build(
+        target_info,
+        default_bundle_directory,
+        &osdk_output_directory,
+        &cargo_target_directory,
+        &config,
+        ActionChoice::Run,
+        &[],
+    );
+
+    if bundle.run_pre_hook(&config, ActionChoice::Run).is_err() {
+        return Err("the pre-run hook failed".to_owned());
+    }
+    let booted = if scenario.steps.is_empty() {
+        boot(&bundle, &config, scenario.timeout)
+    } else {
+        boot_with_agent(&bundle, &config, scenario)
+    };
+    let result = booted.and_then(|(output, exit_code)| scenario.check(&output, exit_code, bless));
+    let hook_result = result.as_ref().map(|_| ()).map_err(|_| 1);
+    if bundle
+        .run_post_hook(&config, ActionChoice::Run, hook_result)
+        .is_err()
+        && result.is_ok()
+    {
+        return Err("the post-run hook failed".to_owned());
+    }
+
+    result
+}
+
+/// Boots the kernel and returns the output and the exit code that OSDK would exit with.
+pub(super) fn boot(
+    bundle: &Bundle,
+    config: &Config,
+    timeout: Option<Duration>,
+) -> Result<(String, i32), String> {
+    let mut qemu = spawn_qemu(bundle, config, Stdio::null());
+
+    let mut stdout = qemu.stdout.take().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = stdout.read_to_end(&mut output);
+        let _ = sender.send(decode_output(&output));
+    });
+
+    let received = match timeout {
+        Some(timeout) => receiver.recv_timeout(timeout),
+        None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+    };
+    let output = match received {
+        Ok(output) => output,
+        Err(RecvTimeoutError::Timeout) => {
+            let _ = qemu.kill();
+            let _ = qemu.wait();
+            return Err(format!(
+                "the kernel timed out after {} seconds",
+                timeout.unwrap().as_secs()
+            ));
+        }
+        Err(RecvTimeoutError::Disconnected) => String::new(),
+    };
+
+    let exit_code = wait_for_exit_code(qemu, &output);
+    Ok((output, exit_code))
+}
+
+/// Boots the kernel, executes the steps of the scenario with the guest agent,
+/// and returns the transcript and the exit code that OSDK would exit with.
+fn boot_with_agent(
+    bundle: &Bundle,
+    config: &Config,
+    scenario: &Scenario,
+) -> Result<(String, i32), String> {
+    let mut qemu = spawn_qemu(bundle, config, Stdio::piped());
+
+    let mut session = AgentSession::new(
+        qemu.stdin.take().unwrap(),
+        qemu.stdout.take().unwrap(),
+        scenario.timeout,
+    );
+    let transcript = match session
+        .wait_ready()
+        .and_then(|()| scenario.run_steps(&mut session))
+    {
+        Ok(transcript) => transcript,
+        Err(message) => {
+            let _ = qemu.kill();
+            let _ = qemu.wait();
+            if PanicReport::parse(session.console()).is_some() {
+                return Err(format!("{} because the kernel panicked", message));
+            }
+            return Err(message);
+        }
+    };
+
+    let output = match session.quit(0) {
+        Ok(output) => output,
+        Err(message) => {
+            let _ = qemu.kill();
+            let _ = qemu.wait();
+            return Err(message);
+        }
+    };
+    Ok((transcript, wait_for_exit_code(qemu, &output)))
+}
+
+pub(super) fn spawn_qemu(bundle: &Bundle, config: &Config, stdin: Stdio) -> Child {
+    let mut qemu_cmd = bundle.qemu_command(config, ActionChoice::Run);
+    qemu_cmd.stdin(stdin).stdout(Stdio::piped());
+    info!("Running QEMU: {:#?}", qemu_cmd);
+    qemu_cmd.spawn().unwrap_or_else(|err| {
+        exit_on_launch_failure(
+            &qemu_cmd.get_program().to_string_lossy(),
+            err,
+            &qemu_install_help(config),
+        )
+    })
+}
+
+/// Waits for QEMU to exit and returns the exit code that OSDK would exit with.
+fn wait_for_exit_code(mut qemu: Child, output: &str) -> i32 {
+    let exit_status = qemu.wait().unwrap();
+    if PanicReport::parse(output).is_some() {
+        Errno::KernelPanic as i32
+    } else {
+        kernel_exit_result(exit_status).err().unwrap_or(0)
+    }
+}
+
+/// Returns the line-by-line difference between the expected and the observed
+/// lines, or `None` if they are the same.
+///
+/// The removed lines are prefixed with `-` and the added lines with `+`.
+fn diff_lines(expected: &[String], observed: &[String]) -> Option<String> {
+    if expected == observed {
+        return None;
+    }
+
+    // The lengths of the longest common subsequences of the suffixes.
+    let (m, n) = (expected.len(), observed.len());
+    let mut lcs = vec![vec![0usize; n + 1]; m + 1];
+    for i in (0..m).rev() {
+        for j in (0..n).rev() {
+            lcs[i][j] = if expected[i] == observed[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < m || j < n {
+        if i < m && j < n && expected[i] == observed[j] {
+            diff.push(format!("  {}", expected[i]));
+            i += 1;
+            j += 1;
+        } else if j == n || (i < m && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push(format!("- {}", expected[i]));
+            i += 1;
+        } else {
+            diff.push(format!("+ {}", observed[j]));
+            j += 1;
+        }
+    }
+    Some(diff.join("\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_owned).collect()
+    }
+
+    fn scenario(content: &str) -> Scenario {
+        Scenario::from_file(
+            Path::new("/scenarios/hello.toml"),
+            toml::from_str(content).unwrap(),
+        )
+    }
+
+    #[test]
+    fn diff_changed_lines() {
+        assert_eq!(diff_lines(&lines("a\nb"), &lines("a\nb")), None);
+        assert_eq!(
+            diff_lines(&lines("a\nb\nc"), &lines("a\nx\nc\nd")).unwrap(),
+            "  a\n- b\n+ x\n  c\n+ d"
+        );
+    }
+
+    #[test]
+    fn filter_scenario_output() {
+        let scenario = scenario(
+            r#"
+init_args = ["sh", "-c", "echo hello"]
+start_after = "^~ # "
+ignore = ["^\\[ *[0-9.]+\\]"]
+"#,
+        );
+        assert_eq!(scenario.golden, Path::new("/scenarios/hello.golden"));
+        assert_eq!(
+            scenario
+                .filter_output("boot\r\n~ # echo\r\n\x1b[32mhello\x1b[0m \r\n[  1.5] log\nbye\n"),
+            ["hello", "bye"]
+        );
+    }
+
+    #[test]
+    fn check_scenario_outcome() {
+        let scenario = scenario(
+            r#"
+init_args = ["true"]
+exit_code = 1
+expect = ["^missing$"]
+golden = "golden/none.golden"
+"#,
+        );
+        let problems = scenario.check("output\n", 0, false).unwrap_err();
+        assert_eq!(problems.lines().count(), 3);
+        assert!(problems.contains("expected the exit code 1, but got 0"));
+        assert!(problems.contains("`^missing$`"));
+        assert!(problems.contains("/scenarios/golden/none.golden"));
+    }
+
+    #[test]
+    fn load_scenario_steps() {
+        let scenario = scenario(
+            r#"
+init_args = ["/test/osdk_agent/osdk_agent"]
+
+[[steps]]
+put = "data/input.txt"
+to = "/tmp/input.txt"
+mode = 0o755
+
+[[steps]]
+run = ["cat", "/tmp/input.txt"]
+
+[[steps]]
+get = "/tmp/input.txt"
+to = "output.txt"
+"#,
+        );
+        assert_eq!(
+            scenario.steps,
+            [
+                Step::Put {
+                    host: PathBuf::from("/scenarios/data/input.txt"),
+                    guest: "/tmp/input.txt".to_owned(),
+                    mode: 0o755,
+                },
+                Step::Run(vec!["cat".to_owned(), "/tmp/input.txt".to_owned()]),
+                Step::Get {
+                    guest: "/tmp/input.txt".to_owned(),
+                    host: PathBuf::from("/scenarios/output.txt"),
+                },
+            ]
+        );
+
+        let invalid = |content: &str| {
+            Step::from_file(
+                Path::new("/scenarios/hello.toml"),
+                toml::from_str(content).unwrap(),
+            )
+            .unwrap_err()
+        };
+        assert!(invalid("run = []").contains("empty"));
+        assert!(invalid("put = \"a\"").contains("`to` is required"));
+        assert!(invalid("to = \"a\"").contains("is required"));
+        assert!(invalid("run = [\"a\"]\nget = \"b\"").contains("only one"));
+    }
+
+    #[test]
+    fn apply_scenario_init_args() {
+        let scenario = scenario(
+            r#"
+init_args = ["sh", "-c", "echo hello"]
+kcmd_args = ["log_level=debug"]
+"#,
+        );
+        let mut kcmdline = ["log_level=error", "--", "sh", "-l"]
+            .map(str::to_owned)
+            .to_vec();
+        scenario.apply_to(&mut kcmdline);
+        assert_eq!(
+            kcmdline,
+            ["log_level=debug", "--", "sh", "-c", "echo hello"].map(str::to_owned)
+        );
+    }
+}
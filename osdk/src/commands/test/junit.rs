@@ -0,0 +1,153 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The JUnit XML report of the test results, which is understood by most CI systems.
+
+use std::{collections::BTreeMap, fmt::Write, io, path::Path, time::Duration};
+
+use super::runner::{TestOutcome, TestStatus};
+
+/// Writes the JUnit XML report of the test outcomes to the file.
+pub fn write_report(path: &Path, outcomes: &[TestOutcome]) -> io::Result<()> {
+    std::fs::write(path, render(outcomes))
+}
+
+/// Renders the report, in which the tests are grouped into a test suite for each crate.
+fn render(outcomes: &[TestOutcome]) -> String {
+    let mut suites: BTreeMap<&str, Vec<&TestOutcome>> = BTreeMap::new();
+    for outcome in outcomes {
+        let crate_name = outcome.name.split("::").next().unwrap();
+        suites.entry(crate_name).or_default().push(outcome);
+    }
+
+    let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(
+        output,
+        r#"<testsuites name="ktest" tests="{}" failures="{}" time="{}">"#,
+        outcomes.len(),
+        nr_failures(outcomes.iter()),
+        seconds(outcomes.iter().map(|outcome| outcome.duration).sum()),
+    )
+    .unwrap();
+    for (crate_name, outcomes) in suites {
+        writeln!(
+            output,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" time="{}">"#,
+            escape(crate_name),
+            outcomes.len(),
+            nr_failures(outcomes.iter().copied()),
+            seconds(outcomes.iter().map(|outcome| outcome.duration).sum()),
+        )
+        .unwrap();
+        for outcome in outcomes {
+            let (class_name, name) = outcome
+                .name
+                .rsplit_once("::")
+                .unwrap_or((crate_name, &outcome.name));
+            write!(
+                output,
+                r#"    <testcase classname="{}" name="{}" time="{}""#,
+                escape(class_name),
+                escape(name),
+                seconds(outcome.duration),
+            )
+            .unwrap();
+
+            let failure_type = match outcome.status {
+                TestStatus::Passed => {
+                    output.push_str("/>\n");
+                    continue;
+                }
+                TestStatus::Failed => "failure",
+                TestStatus::TimedOut => "timeout",
+            };
+            let summary = outcome.message.lines().next().unwrap_or("");
+            writeln!(
+                output,
+                r#">
+      <failure type="{}" message="{}">{}</failure>
+    </testcase>"#,
+                failure_type,
+                escape(summary),
+                escape(&outcome.message),
+            )
+            .unwrap();
+        }
+        output.push_str("  </testsuite>\n");
+    }
+    output.push_str("</testsuites>\n");
+    output
+}
+
+fn nr_failures<'a>(outcomes: impl Iterator<Item = &'a TestOutcome>) -> usize {
+    outcomes
+        .filter(|outcome| outcome.status != TestStatus::Passed)
+        .count()
+}
+
+fn seconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64())
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render_report() {
+        let outcomes = [
+            TestOutcome {
+                name: "foo::bar::a".to_owned(),
+                status: TestStatus::Passed,
+                duration: Duration::from_millis(12),
+                message: String::new(),
+            },
+            TestOutcome {
+                name: "foo::b".to_owned(),
+                status: TestStatus::TimedOut,
+                duration: Duration::from_secs(60),
+                message: "the test timed out after 60 seconds".to_owned(),
+            },
+            TestOutcome {
+                name: "baz::c".to_owned(),
+                status: TestStatus::Failed,
+                duration: Duration::from_millis(1),
+                message: "[caught panic] assertion `left == right` failed\n  left: <1>".to_owned(),
+            },
+        ];
+
+        assert_eq!(
+            render(&outcomes),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="ktest" tests="3" failures="2" time="60.013">
+  <testsuite name="baz" tests="1" failures="1" time="0.001">
+    <testcase classname="baz" name="c" time="0.001">
+      <failure type="failure" message="[caught panic] assertion `left == right` failed">[caught panic] assertion `left == right` failed
+  left: &lt;1&gt;</failure>
+    </testcase>
+  </testsuite>
+  <testsuite name="foo" tests="2" failures="1" time="60.012">
+    <testcase classname="foo::bar" name="a" time="0.012"/>
+    <testcase classname="foo" name="b" time="60.000">
+      <failure type="timeout" message="the test timed out after 60 seconds">the test timed out after 60 seconds</failure>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+}
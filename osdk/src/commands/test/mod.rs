@@ -0,0 +1,420 @@
+// SPDX-License-Identifier: MPL-2.0
+
+mod junit;
+mod runner;
+
+use std::{fs, path::Path, time::Duration};
+
+use self::runner::{ShardOptions, ShardResults, TestOutcome, TestStatus};
+use super::{
+    build::do_cached_build,
+    metrics::{Metric, MetricsFile},
+    util::DEFAULT_TARGET_RELPATH,
+};
+use crate::{
+    base_crate::{new_base_crate, BaseCrateType},
+    bundle::Bundle,
+    cli::TestArgs,
+    config::{
+        scheme::ActionChoice,
+        unix_args::{apply_kv_array, split_to_kv_array},
+        Config,
+    },
+    error::Errno,
+    error_msg, exit_with_error,
+    util::{get_current_crates, get_target_directory, DirGuard},
+};
+
+pub fn execute_test_command(config: &Config, args: &TestArgs) {
+    // The matrix file is read before changing to the directories of the crates.
+    let matrix = args.matrix.as_ref().map(|path| BootMatrix::load(path));
+    let junit_path = args
+        .junit
+        .as_ref()
+        .map(|path| std::env::current_dir().unwrap().join(path));
+    let metrics_path = args
+        .metrics
+        .as_ref()
+        .map(|path| std::env::current_dir().unwrap().join(path));
+
+    let mut results = ShardResults::default();
+    let crates = get_current_crates();
+    for crate_info in crates {
+        std::env::set_current_dir(crate_info.path).unwrap();
+        results.extend(test_current_crate(config, args, matrix.as_ref()));
+    }
+
+    if let Some(path) = metrics_path {
+        MetricsFile::new(config, test_metrics(&results)).write(&path);
+    }
+    if is_sharded(args) {
+        report_outcomes(&results.outcomes, junit_path.as_deref());
+    }
+}
+
+/// Builds and runs the tests of the current crate.
+///
+/// If the tests are run in shards (see [`is_sharded`]), the results of the
+/// shards are returned. Otherwise, this function exits if the tests fail.
+pub fn test_current_crate(
+    config: &Config,
+    args: &TestArgs,
+    matrix: Option<&BootMatrix>,
+) -> ShardResults {
+    let current_crates = get_current_crates();
+    if current_crates.len() != 1 {
+        exit_with_error!(
+            Errno::TooManyCrates,
+            "The current directory contains more than one crate"
+        );
+    }
+    let current_crate = get_current_crates().remove(0);
+
+    let cargo_target_directory = get_target_directory();
+    let osdk_output_directory = cargo_target_directory.join(DEFAULT_TARGET_RELPATH);
+
+    // A special case is that we use OSDK to test the OSDK test runner crate
+    // itself. We check it by name.
+    let runner_self_test = if current_crate.name == "osdk-test-kernel" {
+        if matches!(option_env!("OSDK_LOCAL_DEV"), Some("1")) {
+            true
+        } else {
+            exit_with_error!(
+                Errno::BadCrateName,
+                "The tested crate name collides with the OSDK test runner crate"
+            );
+        }
+    } else {
+        false
+    };
+
+    let target_crate_dir = new_base_crate(
+        BaseCrateType::Test,
+        osdk_output_directory.join(&current_crate.name),
+        &current_crate.name,
+        &current_crate.path,
+        !runner_self_test,
+    );
+
+    let main_rs_path = target_crate_dir.join("src").join("main.rs");
+
+    let ktest_test_whitelist = match &args.test_name {
+        Some(name) => format!(r#"Some(&["{}"])"#, name),
+        None => r#"None"#.to_string(),
+    };
+
+    let mut ktest_crate_whitelist = vec![current_crate.name.clone()];
+    if let Some(name) = &args.test_name {
+        ktest_crate_whitelist.push(name.clone());
+    }
+
+    // Append the ktest static variable and the runner reference to the
+    // `main.rs` file.
+    let ktest_main_rs = format!(
+        r#"
+
+{}
+
+#[no_mangle]
+pub static KTEST_TEST_WHITELIST: Option<&[&str]> = {};
+#[no_mangle]
+pub static KTEST_CRATE_WHITELIST: Option<&[&str]> = Some(&{:#?});
+
+"#,
+        if runner_self_test {
+            ""
+        } else {
+            "extern crate osdk_test_kernel;"
+        },
+        ktest_test_whitelist,
+        ktest_crate_whitelist,
+    );
+    let mut main_rs_content = fs::read_to_string(&main_rs_path).unwrap();
+    main_rs_content.push_str(&ktest_main_rs);
+    fs::write(&main_rs_path, main_rs_content).unwrap();
+
+    // Build the kernel with the given base crate
+    let default_bundle_directory = osdk_output_directory.join(&current_crate.name);
+    let dir_guard = DirGuard::change_dir(&target_crate_dir);
+    let bundle = do_cached_build(
+        default_bundle_directory,
+        &osdk_output_directory,
+        &cargo_target_directory,
+        config,
+        ActionChoice::Test,
+        &["--cfg ktest", "-C panic=unwind"],
+    );
+    std::env::remove_var("RUSTFLAGS");
+    drop(dir_guard);
+
+    if let Some(matrix) = matrix {
+        run_matrix(&bundle, config, matrix);
+    } else if is_sharded(args) {
+        let options = ShardOptions {
+            nr_shards: args.jobs,
+            timeout: args.timeout.map(Duration::from_secs),
+            log_dir: osdk_output_directory.join(&current_crate.name),
+        };
+        return run_sharded(&bundle, config, &options);
+    } else {
+        bundle.run(config, ActionChoice::Test);
+    }
+    ShardResults::default()
+}
+
+/// Returns whether the tests are run in shards, where OSDK follows the progress
+/// of each test.
+fn is_sharded(args: &TestArgs) -> bool {
+    args.jobs > 1 || args.timeout.is_some() || args.junit.is_some() || args.metrics.is_some()
+}
+
+/// Runs the tests in shards with the run hooks.
+fn run_sharded(bundle: &Bundle, config: &Config, options: &ShardOptions) -> ShardResults {
+    if let Err(exit_code) = bundle.run_pre_hook(config, ActionChoice::Test) {
+        std::process::exit(exit_code);
+    }
+
+    let results = runner::run_in_shards(bundle, config, options);
+
+    let result = if results
+        .outcomes
+        .iter()
+        .all(|outcome| outcome.status == TestStatus::Passed)
+    {
+        Ok(())
+    } else {
+        Err(1)
+    };
+    if let Err(exit_code) = bundle.run_post_hook(config, ActionChoice::Test, result) {
+        // The failures of the tests are reported after all the crates are tested.
+        if result.is_ok() {
+            std::process::exit(exit_code);
+        }
+    }
+    results
+}
+
+/// Returns the metrics of the test run, i.e., the average boot time and the
+/// durations of the passed tests.
+fn test_metrics(results: &ShardResults) -> Vec<Metric> {
+    let mut metrics = Vec::new();
+    if !results.boot_times.is_empty() {
+        let total: Duration = results.boot_times.iter().sum();
+        metrics.push(Metric {
+            name: "boot_time".to_owned(),
+            unit: "s".to_owned(),
+            value: total.as_secs_f64() / results.boot_times.len() as f64,
+            bigger_is_better: false,
+        });
+    }
+    for outcome in results.outcomes.iter() {
+        if outcome.status == TestStatus::Passed {
+            metrics.push(Metric {
+                name: format!("ktest/{}", outcome.name),
+                unit: "ms".to_owned(),
+                value: outcome.duration.as_secs_f64() * 1000.0,
+                bigger_is_better: false,
+            });
+        }
+    }
+    metrics
+}
+
+/// Prints the summary of the outcomes, writes the JUnit report if requested,
+/// and exits if any test fails.
+fn report_outcomes(outcomes: &[TestOutcome], junit_path: Option<&Path>) {
+    let nr_with_status = |status| {
+        outcomes
+            .iter()
+            .filter(|outcome| outcome.status == status)
+            .count()
+    };
+    println!(
+        "
+ktest summary: {} passed; {} failed; {} timed out",
+        nr_with_status(TestStatus::Passed),
+        nr_with_status(TestStatus::Failed),
+        nr_with_status(TestStatus::TimedOut)
+    );
+
+    let failures: Vec<_> = outcomes
+        .iter()
+        .filter(|outcome| outcome.status != TestStatus::Passed)
+        .collect();
+    for failure in failures.iter() {
+        match failure.message.lines().next() {
+            Some(reason) => error_msg!("Failed {}: {}", failure.name, reason),
+            None => error_msg!("Failed {}", failure.name),
+        }
+    }
+
+    if let Some(path) = junit_path {
+        if let Err(err) = junit::write_report(path, outcomes) {
+            exit_with_error!(
+                Errno::RunBundle,
+                "Cannot write the JUnit report {}: {}",
+                path.display(),
+                err
+            );
+        }
+        println!("The JUnit report is written to {}", path.display());
+    }
+
+    if !failures.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// A matrix of boot options to run the tests with.
+///
+/// The kernel is booted once for each combination of the variants. An empty
+/// list of variants stands for a single variant that uses the configured
+/// options as they are. For example,
+///
+/// ```toml
+/// kcmd_args = [[], ["log_level=debug"], ["nosmp", "log_level=error"]]
+/// qemu_args = ["", "-machine q35 -smp 4", "-machine microvm"]
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BootMatrix {
+    /// The kernel command line arguments that are applied to the configured ones.
+    #[serde(default)]
+    kcmd_args: Vec<Vec<String>>,
+    /// The QEMU arguments that are applied to the configured ones.
+    #[serde(default)]
+    qemu_args: Vec<String>,
+}
+
+impl BootMatrix {
+    fn load(path: &Path) -> Self {
+        let content = fs::read_to_string(path).unwrap_or_else(|err| {
+            exit_with_error!(
+                Errno::GetMetadata,
+                "Cannot read the boot matrix {}: {}",
+                path.display(),
+                err
+            );
+        });
+        toml::from_str(&content).unwrap_or_else(|err| {
+            exit_with_error!(
+                Errno::ParseMetadata,
+                "Cannot parse the boot matrix {}: {}",
+                path.display(),
+                err
+            );
+        })
+    }
+
+    /// Returns all the combinations of the variants.
+    fn combinations(&self) -> Vec<(Vec<String>, String)> {
+        let kcmd_args = if self.kcmd_args.is_empty() {
+            vec![Vec::new()]
+        } else {
+            self.kcmd_args.clone()
+        };
+        let qemu_args = if self.qemu_args.is_empty() {
+            vec![String::new()]
+        } else {
+            self.qemu_args.clone()
+        };
+
+        kcmd_args
+            .iter()
+            .flat_map(|kcmd| qemu_args.iter().map(|qemu| (kcmd.clone(), qemu.clone())))
+            .collect()
+    }
+}
+
+/// Boots the test kernel with each combination in the matrix and reports the
+/// failed ones.
+fn run_matrix(bundle: &Bundle, config: &Config, matrix: &BootMatrix) {
+    let combinations = matrix.combinations();
+    let mut failures = Vec::new();
+
+    for (i, (kcmd_args, qemu_args)) in combinations.iter().enumerate() {
+        let description = format!("kcmd_args = {:?}, qemu_args = {:?}", kcmd_args, qemu_args);
+        println!(
+            "[{}/{}] Booting with {}",
+            i + 1,
+            combinations.len(),
+            description
+        );
+
+        let mut config = config.clone();
+        apply_kcmd_args(&mut config.test.boot.kcmdline, kcmd_args);
+        config
+            .test
+            .qemu
+            .apply_qemu_args(&split_to_kv_array(qemu_args));
+
+        if let Err(exit_code) = bundle.try_run(&config, ActionChoice::Test) {
+            failures.push((description, exit_code));
+        }
+    }
+
+    println!(
+        "Boot matrix: {} passed, {} failed",
+        combinations.len() - failures.len(),
+        failures.len()
+    );
+    if failures.is_empty() {
+        return;
+    }
+    for (description, exit_code) in failures.iter() {
+        error_msg!("Failed (exit code {}) with {}", exit_code, description);
+    }
+    std::process::exit(1);
+}
+
+/// Applies the kernel command line arguments to the ones before the `--`
+/// separator, which are followed by the arguments of the init process.
+pub(super) fn apply_kcmd_args(kcmdline: &mut Vec<String>, args: &[String]) {
+    let separator = kcmdline
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(kcmdline.len());
+    let init_args = kcmdline.split_off(separator);
+    apply_kv_array(kcmdline, &args.to_vec(), "=", &[]);
+    kcmdline.extend(init_args);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boot_matrix_combinations() {
+        let matrix: BootMatrix = toml::from_str(
+            r#"
+kcmd_args = [[], ["log_level=debug"]]
+qemu_args = ["", "-machine q35"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(matrix.combinations().len(), 4);
+        assert_eq!(
+            matrix.combinations()[3],
+            (
+                vec!["log_level=debug".to_owned()],
+                "-machine q35".to_owned()
+            )
+        );
+        assert_eq!(BootMatrix::default().combinations().len(), 1);
+    }
+
+    #[test]
+    fn apply_kcmd_args_before_init_args() {
+        let mut kcmdline = ["log_level=error", "--", "sh", "-c"]
+            .map(str::to_owned)
+            .to_vec();
+        apply_kcmd_args(
+            &mut kcmdline,
+            &["log_level=debug".to_owned(), "nosmp".to_owned()],
+        );
+        assert_eq!(
+            kcmdline,
+            ["log_level=debug", "nosmp", "--", "sh", "-c"].map(str::to_owned)
+        );
+    }
+}
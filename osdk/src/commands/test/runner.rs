@@ -0,0 +1,535 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Running the tests in shards with per-test timeouts.
+//!
+//! The tests are split into shards, each of which is run by a separate QEMU
+//! instance. The shard is told to the test kernel with the kernel command line
+//! (see `osdk/deps/test-kernel/src/shard.rs`). OSDK follows the output of each
+//! instance to know which test is running. If a test runs for too long, or the
+//! kernel dies while running a test, the instance is killed, the test is marked
+//! failed, and the shard is restarted from the next test.
+
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::PathBuf,
+    process::{Child, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::apply_kcmd_args;
+use crate::{
+    bundle::{kernel_exit_result, panic_report::PanicReport, qemu_install_help, Bundle},
+    commands::util::{strip_ansi_escapes, OutputDecoder},
+    config::{
+        scheme::{ActionChoice, BootMethod},
+        Config,
+    },
+    diagnostic::exit_on_launch_failure,
+    error::Errno,
+    exit_with_error, warn_msg,
+};
+
+/// The options of running the tests in shards.
+pub struct ShardOptions {
+    /// The number of shards, each of which is run by a QEMU instance.
+    pub nr_shards: usize,
+    /// The time limit of each test.
+    pub timeout: Option<Duration>,
+    /// The directory where the output of each shard is saved.
+    pub log_dir: PathBuf,
+}
+
+/// The outcome of a test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestOutcome {
+    /// The full path of the test, starting with the crate name.
+    pub name: String,
+    pub status: TestStatus,
+    pub duration: Duration,
+    /// The details of the failure.
+    pub message: String,
+}
+
+/// The results of running the tests in shards.
+#[derive(Debug, Default)]
+pub struct ShardResults {
+    pub outcomes: Vec<TestOutcome>,
+    /// The time from launching QEMU to starting the first test, of each boot.
+    pub boot_times: Vec<Duration>,
+}
+
+impl ShardResults {
+    pub fn extend(&mut self, other: ShardResults) {
+        self.outcomes.extend(other.outcomes);
+        self.boot_times.extend(other.boot_times);
+    }
+}
+
+/// The status of a finished test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    TimedOut,
+}
+
+/// Runs the tests in shards in parallel and returns the results of all the shards.
+pub fn run_in_shards(bundle: &Bundle, config: &Config, options: &ShardOptions) -> ShardResults {
+    if options.nr_shards > 1 && config.test.boot.method != BootMethod::QemuDirect {
+        exit_with_error!(
+            Errno::RunBundle,
+            "Running the tests in parallel requires the `qemu-direct` boot method"
+        );
+    }
+    std::fs::create_dir_all(&options.log_dir).unwrap();
+
+    thread::scope(|scope| {
+        let shards: Vec<_> = (0..options.nr_shards)
+            .map(|index| {
+                scope.spawn(move || ShardRunner::new(bundle, config, options, index).run())
+            })
+            .collect();
+        let mut results = ShardResults::default();
+        for shard in shards {
+            results.extend(shard.join().unwrap());
+        }
+        results
+    })
+}
+
+struct ShardRunner<'a> {
+    bundle: &'a Bundle,
+    config: &'a Config,
+    options: &'a ShardOptions,
+    index: usize,
+    log: File,
+    results: ShardResults,
+    /// The number of the tests that have been run in the shard.
+    nr_run_tests: usize,
+}
+
+/// How a boot of the test kernel ends.
+enum BootEnd {
+    /// The kernel exits after running all the tests in the shard.
+    Finished,
+    /// The kernel is killed or dies while running a test.
+    Interrupted,
+}
+
+impl<'a> ShardRunner<'a> {
+    fn new(
+        bundle: &'a Bundle,
+        config: &'a Config,
+        options: &'a ShardOptions,
+        index: usize,
+    ) -> Self {
+        let log_path = options.log_dir.join(format!("ktest-shard-{}.log", index));
+        let log = File::create(&log_path).unwrap_or_else(|err| {
+            exit_with_error!(
+                Errno::RunBundle,
+                "Cannot create the log file {}: {}",
+                log_path.display(),
+                err
+            )
+        });
+
+        Self {
+            bundle,
+            config,
+            options,
+            index,
+            log,
+            results: ShardResults::default(),
+            nr_run_tests: 0,
+        }
+    }
+
+    fn run(mut self) -> ShardResults {
+        while let BootEnd::Interrupted = self.boot() {
+            // The tests that have been run can only be skipped when the kernel
+            // command line is passed to the kernel directly.
+            if self.config.test.boot.method != BootMethod::QemuDirect {
+                warn_msg!(
+                    "The remaining tests are not run since the boot method is not `qemu-direct`"
+                );
+                break;
+            }
+            writeln!(
+                self.log,
+                "\n[OSDK] Restarting the shard from the test #{}",
+                self.nr_run_tests
+            )
+            .unwrap();
+        }
+        self.results
+    }
+
+    /// Boots the test kernel and runs the tests in the shard that have not been run.
+    fn boot(&mut self) -> BootEnd {
+        let mut config = self.config.clone();
+        apply_kcmd_args(
+            &mut config.test.boot.kcmdline,
+            &[
+                format!("ktest.shard={}/{}", self.index, self.options.nr_shards),
+                format!("ktest.skip={}", self.nr_run_tests),
+            ],
+        );
+        if self.options.nr_shards > 1 {
+            config.test.qemu.args = isolate_qemu_args(&config.test.qemu.args);
+            if let Some(path) = config.serial_raw.take() {
+                let mut path = path.into_os_string();
+                path.push(format!(".{}", self.index));
+                config.serial_raw = Some(path.into());
+            }
+        }
+
+        let mut qemu_cmd = self.bundle.qemu_command(&config, ActionChoice::Test);
+        qemu_cmd.stdin(Stdio::null()).stdout(Stdio::piped());
+        info!("Running QEMU: {:#?}", qemu_cmd);
+        let mut qemu = qemu_cmd.spawn().unwrap_or_else(|err| {
+            exit_on_launch_failure(
+                &qemu_cmd.get_program().to_string_lossy(),
+                err,
+                &qemu_install_help(&config),
+            )
+        });
+        let mut launch_time = Some(Instant::now());
+        let output_receiver = self.forward_output(&mut qemu);
+
+        let mut parser = OutputParser::default();
+        let mut output = String::new();
+        let mut running: Option<(String, Instant)> = None;
+        let mut has_failed = false;
+        loop {
+            let received = match (&running, self.options.timeout) {
+                (Some((_, start)), Some(timeout)) => {
+                    output_receiver.recv_timeout(timeout.saturating_sub(start.elapsed()))
+                }
+                _ => output_receiver
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            let text = match received {
+                Ok(text) => text,
+                Err(RecvTimeoutError::Timeout) => {
+                    let _ = qemu.kill();
+                    let _ = qemu.wait();
+                    let (name, start) = running.take().unwrap();
+                    let timeout = self.options.timeout.unwrap();
+                    self.record(
+                        name,
+                        TestStatus::TimedOut,
+                        start.elapsed(),
+                        format!("the test timed out after {} seconds", timeout.as_secs()),
+                    );
+                    return BootEnd::Interrupted;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            output.push_str(&text);
+            for event in parser.feed(&text) {
+                match event {
+                    OutputEvent::Started(name) => {
+                        if let Some(launch_time) = launch_time.take() {
+                            self.results.boot_times.push(launch_time.elapsed());
+                        }
+                        running = Some((name, Instant::now()));
+                    }
+                    OutputEvent::Finished(is_passed) => {
+                        let Some((name, start)) = running.take() else {
+                            continue;
+                        };
+                        let status = if is_passed {
+                            TestStatus::Passed
+                        } else {
+                            has_failed = true;
+                            TestStatus::Failed
+                        };
+                        self.record(name, status, start.elapsed(), String::new());
+                    }
+                    OutputEvent::FailureDetail(fn_name, line) => self.add_detail(&fn_name, &line),
+                }
+            }
+        }
+
+        let exit_status = qemu.wait().unwrap();
+        let panic_message = PanicReport::parse(&output).map(|report| report.summary().to_owned());
+        if let Some((name, start)) = running.take() {
+            let message = match panic_message {
+                Some(message) => format!("the kernel panicked: {}", message),
+                None => format!("the kernel exited unexpectedly ({})", exit_status),
+            };
+            self.record(name, TestStatus::Failed, start.elapsed(), message);
+            return BootEnd::Interrupted;
+        }
+
+        // A failure that is not caused by the failed tests, e.g., a boot failure.
+        let result = kernel_exit_result(exit_status);
+        if result.is_err() && !(result == Err(1) && has_failed) {
+            let message = match panic_message {
+                Some(message) => format!("the kernel panicked: {}", message),
+                None => format!("the kernel failed outside the tests ({})", exit_status),
+            };
+            self.record(
+                format!("ktest-shard-{}", self.index),
+                TestStatus::Failed,
+                Duration::ZERO,
+                message,
+            );
+        }
+        BootEnd::Finished
+    }
+
+    /// Forwards the output of QEMU to the log file and the returned receiver.
+    fn forward_output(&self, qemu: &mut Child) -> Receiver<String> {
+        let mut stdout = qemu.stdout.take().unwrap();
+        let mut log = self.log.try_clone().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buffer = [0u8; 4096];
+            let mut decoder = OutputDecoder::default();
+            loop {
+                let len = match stdout.read(&mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(len) => len,
+                };
+                // The log file keeps the raw bytes of the output.
+                let _ = log.write_all(&buffer[..len]);
+                if sender.send(decoder.decode(&buffer[..len])).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send(decoder.finish());
+        });
+        receiver
+    }
+
+    fn record(&mut self, name: String, status: TestStatus, duration: Duration, message: String) {
+        let status_str = match status {
+            TestStatus::Passed => "ok",
+            TestStatus::Failed => "FAILED",
+            TestStatus::TimedOut => "TIMED OUT",
+        };
+        if self.options.nr_shards > 1 {
+            println!(
+                "[shard {}/{}] test {} ... {}",
+                self.index, self.options.nr_shards, name, status_str
+            );
+        } else {
+            println!("test {} ... {}", name, status_str);
+        }
+
+        self.nr_run_tests += 1;
+        self.results.outcomes.push(TestOutcome {
+            name,
+            status,
+            duration,
+            message,
+        });
+    }
+
+    /// Adds a line of the failure details to the last failed test with the function name.
+    fn add_detail(&mut self, fn_name: &str, line: &str) {
+        let suffix = format!("::{}", fn_name);
+        let Some(outcome) = self.results.outcomes.iter_mut().rev().find(|outcome| {
+            outcome.status == TestStatus::Failed && outcome.name.ends_with(&suffix)
+        }) else {
+            return;
+        };
+        if !outcome.message.is_empty() {
+            outcome.message.push('\n');
+        }
+        outcome.message.push_str(line);
+    }
+}
+
+/// Rewrites the QEMU arguments so that multiple instances can run at the same time.
+///
+/// The disk images are opened in the snapshot mode. The resources that cannot be
+/// shared, i.e., the log file of the console, the VNC display and the forwarded
+/// host ports, are removed. OSDK saves the output of each instance instead.
+fn isolate_qemu_args(args: &str) -> String {
+    let Some(args) = shlex::split(args) else {
+        exit_with_error!(
+            Errno::ParseMetadata,
+            "Failed to parse qemu args: {:#?}",
+            args
+        );
+    };
+
+    let mut isolated: Vec<String> = Vec::with_capacity(args.len() + 1);
+    for arg in args {
+        let arg = match isolated.last().map(String::as_str) {
+            Some("-chardev") => remove_sub_options(&arg, "logfile="),
+            Some("-netdev" | "-nic") => remove_sub_options(&arg, "hostfwd="),
+            Some("-display") if arg.starts_with("vnc") => "none".to_owned(),
+            _ => arg,
+        };
+        isolated.push(arg);
+    }
+    if !isolated.iter().any(|arg| arg == "-snapshot") {
+        isolated.push("-snapshot".to_owned());
+    }
+
+    shlex::try_join(isolated.iter().map(String::as_str)).unwrap()
+}
+
+/// Removes the comma-separated sub-options starting with the prefix.
+fn remove_sub_options(arg: &str, prefix: &str) -> String {
+    arg.split(',')
+        .filter(|opt| !opt.starts_with(prefix))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// An event in the output of the test kernel.
+#[derive(Debug, PartialEq, Eq)]
+enum OutputEvent {
+    /// A test is started.
+    Started(String),
+    /// The running test is finished and whether it is passed.
+    Finished(bool),
+    /// A line of the details of a failed test, with the function name of the test.
+    FailureDetail(String, String),
+}
+
+/// A parser of the output of the test kernel.
+///
+/// The test kernel prints `test <name> ...` before running a test, and
+/// ` ok` or ` FAILED` after that, usually in the same line. The details of
+/// the failures are printed after all the tests in a crate are run, each of
+/// which is started with `---- <location> - <function name> ----`.
+#[derive(Default)]
+struct OutputParser {
+    /// The current line, which has not been terminated.
+    line: String,
+    /// Whether the current line starts a test.
+    is_start_line: bool,
+    is_running: bool,
+    /// The function name of the failed test whose details are being printed.
+    failed_fn_name: Option<String>,
+}
+
+impl OutputParser {
+    fn feed(&mut self, text: &str) -> Vec<OutputEvent> {
+        let mut events = Vec::new();
+        for c in text.chars() {
+            if c == '\n' {
+                self.end_line(&mut events);
+            } else {
+                self.line.push(c);
+            }
+        }
+        // The start of a test is known before its end, which is in the same line.
+        self.check_start(&mut events);
+        events
+    }
+
+    fn check_start(&mut self, events: &mut Vec<OutputEvent>) {
+        if self.is_running || self.is_start_line {
+            return;
+        }
+        let line = strip_ansi_escapes(&self.line);
+        let Some(name) = line
+            .strip_prefix("test ")
+            .and_then(|rest| rest.split_once(" ..."))
+            .map(|(name, _)| name)
+        else {
+            return;
+        };
+        events.push(OutputEvent::Started(name.to_owned()));
+        self.is_start_line = true;
+        self.is_running = true;
+        self.failed_fn_name = None;
+    }
+
+    fn end_line(&mut self, events: &mut Vec<OutputEvent>) {
+        self.check_start(events);
+
+        let line = strip_ansi_escapes(&self.line);
+        let line = line.trim_end();
+        if self.is_running {
+            if line.ends_with(" ok") || (self.is_start_line && line.ends_with("ok")) {
+                events.push(OutputEvent::Finished(true));
+                self.is_running = false;
+            } else if line.ends_with("FAILED") {
+                events.push(OutputEvent::Finished(false));
+                self.is_running = false;
+            }
+        } else if let Some(header) = line
+            .strip_prefix("---- ")
+            .and_then(|rest| rest.strip_suffix(" ----"))
+        {
+            self.failed_fn_name = header
+                .rsplit_once(" - ")
+                .map(|(_, fn_name)| fn_name.to_owned());
+        } else if line.starts_with("test result:") || line.starts_with("running ") {
+            self.failed_fn_name = None;
+        } else if let Some(fn_name) = &self.failed_fn_name {
+            if !line.is_empty() {
+                events.push(OutputEvent::FailureDetail(fn_name.clone(), line.to_owned()));
+            }
+        }
+
+        self.line.clear();
+        self.is_start_line = false;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_test_output() {
+        let mut parser = OutputParser::default();
+        let mut events = parser.feed("\nrunning 3 tests in crate \"foo\"\n\ntest foo::a ...");
+        assert_eq!(events, [OutputEvent::Started("foo::a".to_owned())]);
+
+        events = parser.feed(" \x1b[32mok\x1b[39m\r\ntest foo::b ...");
+        events.extend(parser.feed(" oops\n \x1b[31mFAILED\x1b[39m\ntest foo::c ... ok\n"));
+        events.extend(parser.feed(
+            "\nfailures:\n\n---- foo/src/lib.rs:10:1 - b ----\n\n\
+             [caught panic] oops\n\ntest result: FAILED.\n",
+        ));
+        assert_eq!(
+            events,
+            [
+                OutputEvent::Finished(true),
+                OutputEvent::Started("foo::b".to_owned()),
+                OutputEvent::Finished(false),
+                OutputEvent::Started("foo::c".to_owned()),
+                OutputEvent::Finished(true),
+                OutputEvent::FailureDetail("b".to_owned(), "[caught panic] oops".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn isolate_qemu_args_for_instances() {
+        let args = "-m 8G -display vnc=0.0.0.0:42 \
+                    -chardev stdio,id=mux,mux=on,logfile=qemu.log \
+                    -netdev user,id=net01,hostfwd=tcp::1234-:22 \
+                    -drive if=none,format=raw,id=x0,file=./test/build/ext2.img";
+        assert_eq!(
+            shlex::split(&isolate_qemu_args(args)).unwrap(),
+            [
+                "-m",
+                "8G",
+                "-display",
+                "none",
+                "-chardev",
+                "stdio,id=mux,mux=on",
+                "-netdev",
+                "user,id=net01",
+                "-drive",
+                "if=none,format=raw,id=x0,file=./test/build/ext2.img",
+                "-snapshot",
+            ]
+        );
+    }
+}
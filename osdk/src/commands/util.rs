@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use std::process::Command;
+use std::{fmt::Write as _, process::Command};
 
 use crate::util::get_kernel_crate;
 
@@ -26,6 +26,137 @@ pub fn bin_file_name() -> String {
     get_kernel_crate().name + "-osdk-bin"
 }
 
+/// Enables the offline mode, in which the network is never accessed.
+///
+/// The settings are inherited by all the Cargo and rustup processes spawned by OSDK, so that
+/// Cargo only uses the locally cached crates and rustup does not install missing toolchains.
+pub fn enable_offline_mode() {
+    std::env::set_var("CARGO_NET_OFFLINE", "true");
+    std::env::set_var("RUSTUP_AUTO_INSTALL", "0");
+}
+
+pub fn is_offline() -> bool {
+    std::env::var("CARGO_NET_OFFLINE").is_ok_and(|s| s == "true")
+}
+
 pub(crate) fn is_tdx_enabled() -> bool {
     std::env::var("INTEL_TDX").is_ok_and(|s| s == "1")
 }
+
+/// Removes the ANSI escape sequences for the colors.
+pub fn strip_ansi_escapes(line: &str) -> String {
+    let mut stripped = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            stripped.push(c);
+            continue;
+        }
+        // Skip the control sequence, which is ended with a letter.
+        for c in chars.by_ref() {
+            if c.is_ascii_alphabetic() {
+                break;
+            }
+        }
+    }
+    stripped
+}
+
+/// A decoder of the console output of the guest, which may not be valid UTF-8.
+///
+/// The output is received in chunks, so a character may be split across them.
+/// The incomplete character at the end of a chunk is kept until the next chunk
+/// arrives, and the invalid bytes are escaped as `\xNN`.
+#[derive(Default)]
+pub struct OutputDecoder {
+    pending: Vec<u8>,
+}
+
+impl OutputDecoder {
+    /// Decodes the chunk, returning the text of the complete characters.
+    pub fn decode(&mut self, chunk: &[u8]) -> String {
+        self.pending.extend_from_slice(chunk);
+        let mut text = String::with_capacity(self.pending.len());
+        let mut rest = &self.pending[..];
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(valid) => {
+                    text.push_str(valid);
+                    rest = &[];
+                    break;
+                }
+                Err(err) => {
+                    let (valid, invalid) = rest.split_at(err.valid_up_to());
+                    text.push_str(std::str::from_utf8(valid).unwrap());
+                    // An incomplete character may be completed by the next chunk.
+                    let Some(len) = err.error_len() else {
+                        rest = invalid;
+                        break;
+                    };
+                    escape_bytes(&invalid[..len], &mut text);
+                    rest = &invalid[len..];
+                }
+            }
+        }
+        self.pending.drain(..self.pending.len() - rest.len());
+        text
+    }
+
+    /// Finishes the decoding, returning the text of the remaining bytes.
+    pub fn finish(&mut self) -> String {
+        let mut text = String::new();
+        escape_bytes(&self.pending, &mut text);
+        self.pending.clear();
+        text
+    }
+}
+
+/// Decodes the complete output of the guest, escaping the invalid bytes.
+///
+/// See [`OutputDecoder`] for the details.
+pub fn decode_output(output: &[u8]) -> String {
+    let mut decoder = OutputDecoder::default();
+    let mut text = decoder.decode(output);
+    text.push_str(&decoder.finish());
+    text
+}
+
+fn escape_bytes(bytes: &[u8], text: &mut String) {
+    for byte in bytes {
+        text.push_str(&format!("\\x{:02x}", byte));
+    }
+}
+
+/// Encodes the bytes as a lowercase hexadecimal string.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode_split_characters() {
+        let mut decoder = OutputDecoder::default();
+        let bytes = "a€b".as_bytes();
+        assert_eq!(decoder.decode(&bytes[..2]), "a");
+        assert_eq!(decoder.decode(&bytes[2..3]), "");
+        assert_eq!(decoder.decode(&bytes[3..]), "€b");
+        assert_eq!(decoder.finish(), "");
+    }
+
+    #[test]
+    fn decode_invalid_bytes() {
+        let mut decoder = OutputDecoder::default();
+        assert_eq!(decoder.decode(b"a\xff\x80b\xe2\x82"), "a\\xff\\x80b");
+        assert_eq!(decoder.decode(b"c"), "\\xe2\\x82c");
+        assert_eq!(decoder.decode(b"\xe2"), "");
+        assert_eq!(decoder.finish(), "\\xe2");
+
+        assert_eq!(decode_output(b"\x1b[0m\x00\xc3"), "\x1b[0m\x00\\xc3");
+    }
+}
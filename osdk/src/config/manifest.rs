@@ -4,15 +4,14 @@ use std::{
     collections::HashMap,
     fmt, fs,
     path::{Path, PathBuf},
-    process,
 };
 
 use clap::ValueEnum;
 use serde::{de, Deserialize, Deserializer, Serialize};
 
-use super::scheme::Scheme;
+use super::{scheme::Scheme, validate::exit_on_toml_error};
 
-use crate::{error::Errno, error_msg, util::get_cargo_metadata};
+use crate::{error::Errno, exit_with_error, util::get_cargo_metadata};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OsdkMeta {
@@ -67,8 +66,10 @@ impl TomlManifest {
         };
 
         let Some(mut current_manifest) = current_manifest else {
-            error_msg!("Cannot find `OSDK.toml` in the current directory or the workspace root");
-            process::exit(Errno::GetMetadata as _);
+            exit_with_error!(
+                Errno::GetMetadata,
+                "Cannot find `OSDK.toml` in the current directory or the workspace root"
+            );
         };
 
         // All the schemes should inherit from the default scheme.
@@ -84,8 +85,14 @@ impl TomlManifest {
         if let Some(scheme) = scheme {
             let selected_scheme = self.map.get(&scheme.to_string());
             if selected_scheme.is_none() {
-                error_msg!("Scheme `{}` not found in `OSDK.toml`", scheme.to_string());
-                process::exit(Errno::ParseMetadata as _);
+                let mut available: Vec<_> = self.map.keys().map(String::as_str).collect();
+                available.sort_unstable();
+                exit_with_error!(
+                    Errno::ParseMetadata,
+                    "Scheme `{}` not found in `OSDK.toml`, the available schemes are: [{}]",
+                    scheme.to_string(),
+                    available.join(", ")
+                );
             }
             selected_scheme.unwrap()
         } else {
@@ -100,27 +107,16 @@ fn deserialize_toml_manifest(path: impl AsRef<Path>) -> Option<TomlManifest> {
     }
     // Read the file content
     let contents = fs::read_to_string(&path).unwrap_or_else(|err| {
-        error_msg!(
+        exit_with_error!(
+            Errno::GetMetadata,
             "Cannot read file {}, {}",
             path.as_ref().to_string_lossy(),
             err,
         );
-        process::exit(Errno::GetMetadata as _);
     });
     // Parse the TOML content
-    let mut manifest: TomlManifest = toml::from_str(&contents).unwrap_or_else(|err| {
-        let span = err.span().unwrap();
-        let wider_span =
-            (span.start as isize - 20).max(0) as usize..(span.end + 20).min(contents.len());
-        error_msg!(
-            "Cannot parse TOML file, {}. {}:{:?}:\n {}",
-            err.message(),
-            path.as_ref().to_string_lossy(),
-            span,
-            &contents[wider_span],
-        );
-        process::exit(Errno::ParseMetadata as _);
-    });
+    let mut manifest: TomlManifest = toml::from_str(&contents)
+        .unwrap_or_else(|err| exit_on_toml_error(path.as_ref(), &contents, &err));
 
     // Preprocess the parsed manifest
     let cwd = path.as_ref().parent().unwrap();
@@ -140,24 +136,30 @@ impl<'de> Deserialize<'de> for TomlManifest {
         enum Field {
             ProjectType,
             SupportedArchs,
+            Arch,
             Boot,
             Grub,
             Qemu,
             Build,
             Run,
             Test,
+            Debug,
+            DiskImage,
             Scheme,
         }
 
         const EXPECTED: &[&str] = &[
             "project_type",
             "supported_archs",
+            "arch",
             "boot",
             "grub",
             "qemu",
             "build",
             "run",
             "test",
+            "debug",
+            "disk_image",
             "scheme",
         ];
 
@@ -182,12 +184,15 @@ impl<'de> Deserialize<'de> for TomlManifest {
                         match v {
                             "project_type" => Ok(Field::ProjectType),
                             "supported_archs" => Ok(Field::SupportedArchs),
+                            "arch" => Ok(Field::Arch),
                             "boot" => Ok(Field::Boot),
                             "grub" => Ok(Field::Grub),
                             "qemu" => Ok(Field::Qemu),
                             "build" => Ok(Field::Build),
                             "run" => Ok(Field::Run),
                             "test" => Ok(Field::Test),
+                            "debug" => Ok(Field::Debug),
+                            "disk_image" => Ok(Field::DiskImage),
                             "scheme" => Ok(Field::Scheme),
                             _ => Err(de::Error::unknown_field(v, EXPECTED)),
                         }
@@ -219,8 +224,11 @@ impl<'de> Deserialize<'de> for TomlManifest {
                     ($field:ident) => {{
                         let value = map.next_value()?;
                         if default_scheme.$field.is_some() {
-                            error_msg!("Duplicated field `{}`", stringify!($field));
-                            process::exit(Errno::ParseMetadata as _);
+                            exit_with_error!(
+                                Errno::ParseMetadata,
+                                "Duplicated field `{}`",
+                                stringify!($field)
+                            );
                         }
                         default_scheme.$field = Some(value);
                     }};
@@ -229,8 +237,11 @@ impl<'de> Deserialize<'de> for TomlManifest {
                     ($field:ident) => {{
                         let value = map.next_value()?;
                         if !default_scheme.$field.is_empty() {
-                            error_msg!("Duplicated field `{}`", stringify!($field));
-                            process::exit(Errno::ParseMetadata as _);
+                            exit_with_error!(
+                                Errno::ParseMetadata,
+                                "Duplicated field `{}`",
+                                stringify!($field)
+                            );
                         }
                         default_scheme.$field = value;
                     }};
@@ -243,12 +254,15 @@ impl<'de> Deserialize<'de> for TomlManifest {
                             project_type = Some(value);
                         }
                         Field::SupportedArchs => match_and_add_vec!(supported_archs),
+                        Field::Arch => match_and_add_option!(arch),
                         Field::Boot => match_and_add_option!(boot),
                         Field::Grub => match_and_add_option!(grub),
                         Field::Qemu => match_and_add_option!(qemu),
                         Field::Build => match_and_add_option!(build),
                         Field::Run => match_and_add_option!(run),
                         Field::Test => match_and_add_option!(test),
+                        Field::Debug => match_and_add_option!(debug),
+                        Field::DiskImage => match_and_add_option!(disk_image),
                         Field::Scheme => {
                             let scheme: HashMap<String, Scheme> = map.next_value()?;
                             scheme_map = scheme;
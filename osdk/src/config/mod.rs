@@ -8,6 +8,7 @@
 pub mod manifest;
 pub mod scheme;
 pub mod unix_args;
+mod validate;
 
 #[cfg(test)]
 mod test;
@@ -18,17 +19,18 @@ use std::{
     process,
 };
 
-use linux_bzimage_builder::PayloadEncoding;
 use scheme::{
-    Action, ActionScheme, BootProtocol, BootScheme, Build, GrubScheme, QemuScheme, Scheme,
+    Action, ActionScheme, BootMethod, BootScheme, Build, DebugConfig, DiskImage, GrubScheme,
+    QemuScheme, Scheme,
 };
 
 use crate::{
     arch::{get_default_arch, Arch},
     cli::CommonArgs,
     config::unix_args::apply_kv_array,
+    diagnostic::emit_all,
     error::Errno,
-    error_msg,
+    exit_with_error,
 };
 
 /// The global configuration for the OSDK actions.
@@ -39,6 +41,13 @@ pub struct Config {
     pub build: Build,
     pub run: Action,
     pub test: Action,
+    #[serde(default)]
+    pub debug: DebugConfig,
+    #[serde(default)]
+    pub disk_image: DiskImage,
+    /// The file that receives the raw bytes written to the second serial port of the guest.
+    #[serde(default)]
+    pub serial_raw: Option<PathBuf>,
 }
 
 fn apply_args_before_finalize(
@@ -70,14 +79,20 @@ fn apply_args_before_finalize(
         }
         if let Some(initramfs) = &args.initramfs {
             let Ok(initramfs) = initramfs.canonicalize() else {
-                error_msg!("The initramfs path provided with argument `--initramfs` does not match any files.");
-                process::exit(Errno::GetMetadata as _);
+                exit_with_error!(Errno::GetMetadata, "The initramfs path provided with argument `--initramfs` does not match any files.");
             };
             boot.initramfs = Some(initramfs);
         }
         if let Some(boot_method) = args.boot_method {
             boot.method = Some(boot_method);
         }
+        if let Some(direct_boot_protocol) = args.direct_boot_protocol {
+            boot.protocol = Some(direct_boot_protocol);
+        }
+        if let Some(boot_protocol) = args.boot_protocol {
+            boot.method = Some(BootMethod::QemuDirect);
+            boot.protocol = Some(boot_protocol);
+        }
     }
 
     if action_scheme.qemu.is_none() {
@@ -86,10 +101,10 @@ fn apply_args_before_finalize(
     if let Some(ref mut qemu) = action_scheme.qemu {
         if let Some(path) = &args.qemu_exe {
             let Ok(qemu_path) = path.canonicalize() else {
-                error_msg!(
+                exit_with_error!(
+                    Errno::GetMetadata,
                     "The QEMU path provided with argument `--qemu-exe` does not match any files."
                 );
-                process::exit(Errno::GetMetadata as _);
             };
             qemu.path = Some(qemu_path);
         }
@@ -107,13 +122,13 @@ fn canonicalize_and_eval(action_scheme: &mut ActionScheme, workdir: &PathBuf) {
         std::env::set_current_dir(workdir).unwrap();
 
         *target = target.canonicalize().unwrap_or_else(|err| {
-            error_msg!(
+            std::env::set_current_dir(&last_cwd).unwrap();
+            exit_with_error!(
+                Errno::GetMetadata,
                 "Cannot canonicalize path `{}`: {}",
                 target.to_string_lossy(),
                 err,
             );
-            std::env::set_current_dir(&last_cwd).unwrap();
-            process::exit(Errno::GetMetadata as _);
         });
         std::env::set_current_dir(last_cwd).unwrap();
     };
@@ -144,8 +159,11 @@ fn canonicalize_and_eval(action_scheme: &mut ActionScheme, workdir: &PathBuf) {
             *args = match eval(workdir, args) {
                 Ok(v) => v,
                 Err(e) => {
-                    error_msg!("Failed to evaluate qemu args: {:#?}", e);
-                    process::exit(Errno::ParseMetadata as _);
+                    exit_with_error!(
+                        Errno::ParseMetadata,
+                        "Failed to evaluate qemu args: {:#?}",
+                        e
+                    );
                 }
             }
         }
@@ -180,18 +198,31 @@ fn apply_args_after_finalize(action: &mut Action, args: &CommonArgs) {
 }
 
 impl Config {
+    /// Creates the effective configuration from the scheme and the command line arguments.
+    ///
+    /// The constraints between the fields are checked, and OSDK exits if any of them is
+    /// violated.
     pub fn new(scheme: &Scheme, common_args: &CommonArgs) -> Self {
-        let check_compatibility = |protocol: BootProtocol, encoding: PayloadEncoding| {
-            if protocol != BootProtocol::Linux && encoding != PayloadEncoding::Raw {
-                panic!("The encoding format is not allowed to be specified if the boot protocol is not {:#?}", BootProtocol::Linux);
-            }
-        };
-        let target_arch = common_args.target_arch.unwrap_or(get_default_arch());
+        let target_arch = common_args
+            .target_arch
+            .or(scheme.arch)
+            .unwrap_or_else(get_default_arch);
+        if !scheme.supported_archs.is_empty() && !scheme.supported_archs.contains(&target_arch) {
+            let supported_archs: Vec<_> =
+                scheme.supported_archs.iter().map(Arch::to_string).collect();
+            exit_with_error!(
+                Errno::Cli,
+                "The scheme does not support the architecture `{}`, the supported ones are: [{}]",
+                target_arch,
+                supported_archs.join(", ")
+            );
+        }
         let default_scheme = ActionScheme {
             boot: scheme.boot.clone(),
             grub: scheme.grub.clone(),
             qemu: scheme.qemu.clone(),
             build: scheme.build.clone(),
+            hooks: None,
         };
         let build = {
             let mut build = scheme.build.clone().unwrap_or_default().finalize();
@@ -204,7 +235,6 @@ impl Config {
             apply_args_before_finalize(&mut run, common_args, scheme.work_dir.as_ref().unwrap());
             let mut run = run.finalize(target_arch);
             apply_args_after_finalize(&mut run, common_args);
-            check_compatibility(run.grub.boot_protocol, run.build.encoding.clone());
             run
         };
         let test = {
@@ -213,18 +243,37 @@ impl Config {
             apply_args_before_finalize(&mut test, common_args, scheme.work_dir.as_ref().unwrap());
             let mut test = test.finalize(target_arch);
             apply_args_after_finalize(&mut test, common_args);
-            check_compatibility(test.grub.boot_protocol, test.build.encoding.clone());
             test
         };
-        Self {
-            work_dir: scheme
-                .work_dir
-                .clone()
-                .unwrap_or_else(|| env::current_dir().unwrap()),
+        let work_dir = scheme
+            .work_dir
+            .clone()
+            .unwrap_or_else(|| env::current_dir().unwrap());
+        let disk_image = {
+            let mut disk_image = scheme.disk_image.clone().unwrap_or_default().finalize();
+            // The partition images are relative to the manifest.
+            for partition in disk_image.partitions.iter_mut() {
+                if let Some(image) = partition.image.as_mut() {
+                    *image = work_dir.join(&*image);
+                }
+            }
+            disk_image
+        };
+        let config = Self {
+            work_dir,
             target_arch,
             build,
             run,
             test,
-        }
+            debug: scheme.debug.clone().unwrap_or_default().finalize(),
+            disk_image,
+            // QEMU runs in the working directory, so the path is resolved beforehand.
+            serial_raw: common_args
+                .serial_raw
+                .as_ref()
+                .map(|path| env::current_dir().unwrap().join(path)),
+        };
+        emit_all(&validate::check_config(&config));
+        config
     }
 }
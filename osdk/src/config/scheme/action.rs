@@ -2,7 +2,9 @@
 
 use linux_bzimage_builder::PayloadEncoding;
 
-use super::{inherit_optional, Boot, BootScheme, Grub, GrubScheme, Qemu, QemuScheme};
+use super::{
+    inherit_optional, Boot, BootScheme, Grub, GrubScheme, Hooks, HooksScheme, Qemu, QemuScheme,
+};
 
 use crate::{cli::CommonArgs, config::Arch};
 
@@ -13,6 +15,7 @@ pub enum ActionChoice {
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct BuildScheme {
     pub profile: Option<String>,
     #[serde(default)]
@@ -118,11 +121,13 @@ impl BuildScheme {
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct ActionScheme {
     pub boot: Option<BootScheme>,
     pub grub: Option<GrubScheme>,
     pub qemu: Option<QemuScheme>,
     pub build: Option<BuildScheme>,
+    pub hooks: Option<HooksScheme>,
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -131,6 +136,7 @@ pub struct Action {
     pub grub: Grub,
     pub qemu: Qemu,
     pub build: Build,
+    pub hooks: Hooks,
 }
 
 impl ActionScheme {
@@ -139,6 +145,7 @@ impl ActionScheme {
         inherit_optional!(from, self, .grub);
         inherit_optional!(from, self, .qemu);
         inherit_optional!(from, self, .build);
+        inherit_optional!(from, self, .hooks);
     }
 
     pub fn finalize(self, arch: Arch) -> Action {
@@ -147,6 +154,7 @@ impl ActionScheme {
             grub: self.grub.unwrap_or_default().finalize(),
             qemu: self.qemu.unwrap_or_default().finalize(arch),
             build: self.build.unwrap_or_default().finalize(),
+            hooks: self.hooks.unwrap_or_default().finalize(),
         }
     }
 }
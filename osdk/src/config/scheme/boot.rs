@@ -5,6 +5,7 @@ use clap::ValueEnum;
 use std::path::PathBuf;
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct BootScheme {
     /// Command line arguments for the guest kernel
     #[serde(default)]
@@ -16,6 +17,8 @@ pub struct BootScheme {
     pub initramfs: Option<PathBuf>,
     /// The infrastructures used to boot the guest
     pub method: Option<BootMethod>,
+    /// The protocol used to boot the kernel if the method is `qemu-direct`
+    pub protocol: Option<DirectBootProtocol>,
 }
 
 #[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
@@ -31,11 +34,37 @@ pub enum BootMethod {
     QemuDirect,
 }
 
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum DirectBootProtocol {
+    /// Boot the kernel ELF directly.
+    ///
+    /// On x86-64, QEMU loads the ELF with the Multiboot protocol.
+    #[default]
+    Elf,
+    /// Boot a `bzImage` converted from the kernel ELF with the EFI handover protocol
+    /// of Linux.
+    ///
+    /// QEMU must be started with a UEFI firmware, e.g., OVMF.
+    LinuxEfiHandover,
+    /// Boot a `bzImage` converted from the kernel ELF with the legacy 32-bit boot
+    /// protocol of Linux.
+    LinuxLegacy,
+}
+
+impl DirectBootProtocol {
+    /// Returns whether the kernel is booted as a `bzImage` with the Linux boot protocol.
+    pub fn is_linux(&self) -> bool {
+        matches!(self, Self::LinuxEfiHandover | Self::LinuxLegacy)
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Boot {
     pub kcmdline: Vec<String>,
     pub initramfs: Option<PathBuf>,
     pub method: BootMethod,
+    pub protocol: DirectBootProtocol,
 }
 
 impl BootScheme {
@@ -56,6 +85,9 @@ impl BootScheme {
         if self.method.is_none() {
             self.method = from.method;
         }
+        if self.protocol.is_none() {
+            self.protocol = from.protocol;
+        }
     }
 
     pub fn finalize(self) -> Boot {
@@ -66,6 +98,7 @@ impl BootScheme {
             kcmdline,
             initramfs: self.initramfs,
             method: self.method.unwrap_or(BootMethod::QemuDirect),
+            protocol: self.protocol.unwrap_or(DirectBootProtocol::Elf),
         }
     }
 }
@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use clap::ValueEnum;
+
+/// The default address on which the QEMU GDB server listens.
+pub const DEFAULT_GDB_SERVER_ADDR: &str = ".osdk-gdb-socket";
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DebugScheme {
+    /// The address on which the QEMU GDB server listens, which is
+    /// either a path of a UNIX domain socket or `[IP]:PORT`
+    pub gdb_server_addr: Option<String>,
+    /// The debugger spawned to attach to the GDB server
+    pub debugger: Option<Debugger>,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Debugger {
+    /// Attach with GDB.
+    #[default]
+    Gdb,
+    /// Attach with LLDB, which supports TCP GDB server addresses only.
+    Lldb,
+    /// Do not spawn a debugger, but wait for one to attach manually.
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DebugConfig {
+    pub gdb_server_addr: String,
+    pub debugger: Debugger,
+}
+
+impl Default for DebugConfig {
+    fn default() -> Self {
+        DebugConfig {
+            gdb_server_addr: DEFAULT_GDB_SERVER_ADDR.to_owned(),
+            debugger: Debugger::default(),
+        }
+    }
+}
+
+impl DebugScheme {
+    pub fn inherit(&mut self, from: &Self) {
+        if self.gdb_server_addr.is_none() {
+            self.gdb_server_addr.clone_from(&from.gdb_server_addr);
+        }
+        if self.debugger.is_none() {
+            self.debugger = from.debugger;
+        }
+    }
+
+    pub fn finalize(self) -> DebugConfig {
+        DebugConfig {
+            gdb_server_addr: self
+                .gdb_server_addr
+                .unwrap_or(DEFAULT_GDB_SERVER_ADDR.to_owned()),
+            debugger: self.debugger.unwrap_or_default(),
+        }
+    }
+}
@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+
+use crate::{error::Errno, exit_with_error};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiskImageScheme {
+    /// The total size of the disk image, e.g., `1G`. Defaults to the
+    /// smallest size that fits all the partitions
+    pub size: Option<String>,
+    /// The size of the EFI system partition. Defaults to the larger one of
+    /// 64 MiB and the size that fits the boot files
+    pub esp_size: Option<String>,
+    /// The partitions placed after the EFI system partition
+    #[serde(default)]
+    pub partitions: Vec<PartitionScheme>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartitionScheme {
+    /// The name of the partition in the GPT
+    pub label: String,
+    /// The size of the partition. Defaults to the size of `image`
+    pub size: Option<String>,
+    /// The path of a file system image to fill the partition with
+    pub image: Option<PathBuf>,
+}
+
+/// The format of the disk image produced by `cargo osdk build --disk-image`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiskImageFormat {
+    Raw,
+    Qcow2,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiskImage {
+    /// The total size in bytes, or `None` to fit all the partitions.
+    pub size: Option<u64>,
+    /// The size of the EFI system partition in bytes, or `None` to fit the boot files.
+    pub esp_size: Option<u64>,
+    pub partitions: Vec<Partition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Partition {
+    pub label: String,
+    /// The size in bytes, or `None` to fit the image.
+    pub size: Option<u64>,
+    pub image: Option<PathBuf>,
+}
+
+impl DiskImageScheme {
+    pub fn inherit(&mut self, from: &Self) {
+        if self.size.is_none() {
+            self.size.clone_from(&from.size);
+        }
+        if self.esp_size.is_none() {
+            self.esp_size.clone_from(&from.esp_size);
+        }
+        if self.partitions.is_empty() {
+            self.partitions.clone_from(&from.partitions);
+        }
+    }
+
+    pub fn finalize(self) -> DiskImage {
+        DiskImage {
+            size: self.size.as_deref().map(parse_manifest_size),
+            esp_size: self.esp_size.as_deref().map(parse_manifest_size),
+            partitions: self
+                .partitions
+                .into_iter()
+                .map(|partition| Partition {
+                    label: partition.label,
+                    size: partition.size.as_deref().map(parse_manifest_size),
+                    image: partition.image,
+                })
+                .collect(),
+        }
+    }
+}
+
+fn parse_manifest_size(size: &str) -> u64 {
+    parse_size(size).unwrap_or_else(|| {
+        exit_with_error!(
+            Errno::ParseMetadata,
+            "Invalid disk image size `{}`, expected bytes or a number with a `K`, `M` or `G` suffix",
+            size
+        )
+    })
+}
+
+/// Parses a size in bytes, which may have a binary suffix, e.g., `512K`, `64M` or `1G`.
+pub fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let (number, shift) = match size.char_indices().last()? {
+        (i, 'K' | 'k') => (&size[..i], 10),
+        (i, 'M' | 'm') => (&size[..i], 20),
+        (i, 'G' | 'g') => (&size[..i], 30),
+        _ => (size, 0),
+    };
+    number.parse::<u64>().ok()?.checked_mul(1 << shift)
+}
@@ -5,10 +5,13 @@ use clap::ValueEnum;
 use std::path::PathBuf;
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct GrubScheme {
     /// The path of `grub_mkrecue`. Only needed if `boot.method` is `grub`
+    #[serde(alias = "mkrescue_path")]
     pub grub_mkrescue: Option<PathBuf>,
     /// The boot protocol specified in the GRUB configuration
+    #[serde(alias = "protocol")]
     pub boot_protocol: Option<BootProtocol>,
     /// Whether to display the GRUB menu, defaults to `false`
     #[serde(default)]
@@ -0,0 +1,39 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A module about the host commands that run around QEMU.
+
+/// The host commands that run before and after running QEMU.
+///
+/// The commands are executed with `sh -c` in the directory of the manifest.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HooksScheme {
+    /// The command executed before QEMU starts
+    pub pre: Option<String>,
+    /// The command executed after QEMU exits
+    pub post: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hooks {
+    pub pre: Option<String>,
+    pub post: Option<String>,
+}
+
+impl HooksScheme {
+    pub fn inherit(&mut self, from: &Self) {
+        if self.pre.is_none() {
+            self.pre.clone_from(&from.pre);
+        }
+        if self.post.is_none() {
+            self.post.clone_from(&from.post);
+        }
+    }
+
+    pub fn finalize(self) -> Hooks {
+        Hooks {
+            pre: self.pre,
+            post: self.post,
+        }
+    }
+}
@@ -8,26 +8,38 @@ mod action;
 pub use action::*;
 mod boot;
 pub use boot::*;
+mod debug;
+pub use debug::*;
+mod disk_image;
+pub use disk_image::*;
 mod grub;
 pub use grub::*;
+mod hooks;
+pub use hooks::*;
 mod qemu;
 pub use qemu::*;
 
 /// All the configurable fields within a scheme.
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Scheme {
     // The user is not allowed to set this field. However,
     // the manifest loader set this and all actions such
     // as running, testing, and building will use this field.
+    #[serde(skip_deserializing)]
     pub work_dir: Option<PathBuf>,
     #[serde(default)]
     pub supported_archs: Vec<Arch>,
+    /// The architecture to build for if it is not given in the CLI.
+    pub arch: Option<Arch>,
     pub boot: Option<BootScheme>,
     pub grub: Option<GrubScheme>,
     pub qemu: Option<QemuScheme>,
     pub build: Option<BuildScheme>,
     pub run: Option<ActionScheme>,
     pub test: Option<ActionScheme>,
+    pub debug: Option<DebugScheme>,
+    pub disk_image: Option<DiskImageScheme>,
 }
 
 macro_rules! inherit_optional {
@@ -48,22 +60,30 @@ impl Scheme {
         Scheme {
             work_dir: None,
             supported_archs: vec![],
+            arch: None,
             boot: None,
             grub: None,
             qemu: None,
             build: None,
             run: None,
             test: None,
+            debug: None,
+            disk_image: None,
         }
     }
 
     pub fn inherit(&mut self, from: &Self) {
         // Supported archs are not inherited
+        if self.arch.is_none() {
+            self.arch = from.arch;
+        }
         inherit_optional!(from, self, .boot);
         inherit_optional!(from, self, .grub);
         inherit_optional!(from, self, .build);
         inherit_optional!(from, self, .run);
         inherit_optional!(from, self, .test);
+        inherit_optional!(from, self, .debug);
+        inherit_optional!(from, self, .disk_image);
         // The inheritance of `work_dir` depends on `qemu`, so
         // here is a special treatment.
         if let Some(qemu) = &mut self.qemu {
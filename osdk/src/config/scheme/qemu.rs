@@ -2,16 +2,17 @@
 
 //! A module about QEMU settings and arguments.
 
-use std::{path::PathBuf, process};
+use std::path::PathBuf;
 
 use crate::{
     arch::{get_default_arch, Arch},
     config::unix_args::{apply_kv_array, get_key, split_to_kv_array},
+    diagnostic::Diagnostic,
     error::Errno,
-    error_msg,
 };
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct QemuScheme {
     /// The additional arguments for running QEMU, in the form of raw
     /// command line arguments.
@@ -96,13 +97,36 @@ impl QemuScheme {
 
     pub fn finalize(self, arch: Arch) -> Qemu {
         Qemu {
-            args: self.args.unwrap_or_default(),
+            args: self
+                .args
+                .unwrap_or_else(|| arch.default_qemu_args().to_owned()),
             bootdev_append_options: self.bootdev_append_options,
             path: self.path.unwrap_or(PathBuf::from(arch.system_qemu())),
         }
     }
 }
 
+/// Returns the firmware files (e.g., OVMF) that are specified in the QEMU arguments.
+pub fn firmware_files(qemu_args: &str) -> Vec<String> {
+    let args = shlex::split(qemu_args).unwrap_or_default();
+    let mut firmware_files = Vec::new();
+    for (option, value) in args.iter().zip(args.iter().skip(1)) {
+        match option.as_str() {
+            "-bios" | "-pflash" => firmware_files.push(value.clone()),
+            "-drive" if value.split(',').any(|opt| opt == "if=pflash") => {
+                firmware_files.extend(
+                    value
+                        .split(',')
+                        .filter_map(|opt| opt.strip_prefix("file="))
+                        .map(str::to_owned),
+                );
+            }
+            _ => {}
+        }
+    }
+    firmware_files
+}
+
 // Below are checked keys in qemu arguments. The key list is non-exhaustive.
 
 /// Keys with multiple values
@@ -124,19 +148,30 @@ fn check_qemu_arg(arg: &str) {
     };
 
     if NOT_ALLOWED_TO_SET_KEYS.contains(&key.as_str()) {
-        error_msg!("`{}` is not allowed to set", arg);
-        process::exit(Errno::ParseMetadata as _);
+        Diagnostic::error(format!("`{}` is not allowed to set", arg))
+            .with_code(Errno::ParseMetadata)
+            .with_help(
+                "OSDK sets the kernel, the command line and the initramfs from the `boot` options",
+            )
+            .emit_and_exit();
     }
 
     if NO_VALUE_KEYS.contains(&key.as_str()) && key.as_str() != arg {
-        error_msg!("`{}` cannot have value", arg);
-        process::exit(Errno::ParseMetadata as _);
+        Diagnostic::error(format!("`{}` cannot have value", arg))
+            .with_code(Errno::ParseMetadata)
+            .with_help(format!("write `{}` alone", key))
+            .emit_and_exit();
     }
 
     if (SINGLE_VALUE_KEYS.contains(&key.as_str()) || MULTI_VALUE_KEYS.contains(&key.as_str()))
         && key.as_str() == arg
     {
-        error_msg!("`{}` should have value", arg);
-        process::exit(Errno::ParseMetadata as _);
+        Diagnostic::error(format!("`{}` should have value", arg))
+            .with_code(Errno::ParseMetadata)
+            .with_help(format!(
+                "write the value after `{}`, separated by a space",
+                key
+            ))
+            .emit_and_exit();
     }
 }
@@ -15,6 +15,93 @@ fn deserialize_toml_manifest() {
     assert!(type_ == manifest::ProjectType::Kernel);
 }
 
+#[test]
+fn run_hooks() {
+    let content = include_str!("OSDK.toml.full");
+    let toml_manifest: manifest::TomlManifest = toml::from_str(content).unwrap();
+
+    let run = toml_manifest.default_scheme.run.clone().unwrap();
+    let hooks = run.hooks.unwrap().finalize();
+    assert_eq!(hooks.pre.as_deref(), Some("./scripts/setup-tap.sh"));
+    assert_eq!(hooks.post.as_deref(), Some("./scripts/collect-logs.sh"));
+
+    // The hooks of running do not apply to testing.
+    let test = toml_manifest.default_scheme.test.clone().unwrap();
+    assert!(test.hooks.is_none());
+}
+
+#[test]
+fn direct_boot_protocol() {
+    let content = include_str!("OSDK.toml.full");
+    let toml_manifest: manifest::TomlManifest = toml::from_str(content).unwrap();
+    let default_boot = toml_manifest.default_scheme.boot.as_ref().unwrap();
+
+    let mut test_boot = toml_manifest
+        .default_scheme
+        .test
+        .clone()
+        .unwrap()
+        .boot
+        .unwrap();
+    test_boot.inherit(default_boot);
+    let test_boot = test_boot.finalize();
+    assert_eq!(test_boot.method, scheme::BootMethod::QemuDirect);
+    assert_eq!(
+        test_boot.protocol,
+        scheme::DirectBootProtocol::LinuxEfiHandover
+    );
+
+    // The kernel ELF is booted directly by default.
+    let mut run_boot = toml_manifest
+        .default_scheme
+        .run
+        .clone()
+        .unwrap()
+        .boot
+        .unwrap();
+    run_boot.inherit(default_boot);
+    assert_eq!(
+        run_boot.finalize().protocol,
+        scheme::DirectBootProtocol::Elf
+    );
+}
+
+#[test]
+fn disk_image() {
+    let content = include_str!("OSDK.toml.full");
+    let toml_manifest: manifest::TomlManifest = toml::from_str(content).unwrap();
+
+    let disk_image = toml_manifest
+        .default_scheme
+        .disk_image
+        .clone()
+        .unwrap()
+        .finalize();
+    assert_eq!(disk_image.size, Some(1024 * 1024 * 1024));
+    assert_eq!(disk_image.esp_size, Some(128 * 1024 * 1024));
+    assert_eq!(
+        disk_image.partitions,
+        vec![
+            scheme::Partition {
+                label: "ext2".to_owned(),
+                size: None,
+                image: Some(PathBuf::from("ext2.img")),
+            },
+            scheme::Partition {
+                label: "exfat".to_owned(),
+                size: Some(64 * 1024 * 1024),
+                image: None,
+            },
+        ]
+    );
+
+    assert_eq!(scheme::parse_size("4096"), Some(4096));
+    assert_eq!(scheme::parse_size("512k"), Some(512 * 1024));
+    assert_eq!(scheme::parse_size("2G"), Some(2 * 1024 * 1024 * 1024));
+    assert_eq!(scheme::parse_size("1T"), None);
+    assert_eq!(scheme::parse_size("M"), None);
+}
+
 #[test]
 fn conditional_manifest() {
     let tmp_file = "/tmp/osdk_test_file";
@@ -46,6 +133,11 @@ fn conditional_manifest() {
         .as_ref()
         .unwrap()
         .contains(&String::from("-device ioh3420,id=pcie.0,chassis=1",)));
+    let mut debug = scheme.debug.clone().unwrap();
+    debug.inherit(toml_manifest.default_scheme.debug.as_ref().unwrap());
+    let debug = debug.finalize();
+    assert_eq!(debug.gdb_server_addr, "localhost:1234");
+    assert_eq!(debug.debugger, scheme::Debugger::Lldb);
 
     // Tdx
     let scheme = toml_manifest.get_scheme(Some("tdx".to_owned()));
@@ -56,3 +148,147 @@ fn conditional_manifest() {
 
     fs::remove_file(tmp_file).unwrap();
 }
+
+#[test]
+fn target_arch() {
+    let toml_manifest: manifest::TomlManifest = {
+        let content = include_str!("OSDK.toml.full");
+        toml::from_str(content).unwrap()
+    };
+    assert_eq!(toml_manifest.default_scheme.arch, None);
+
+    let mut scheme = toml_manifest.get_scheme(Some("riscv".to_owned())).clone();
+    scheme.inherit(&toml_manifest.default_scheme);
+    assert_eq!(scheme.arch, Some(Arch::RiscV64));
+
+    // The QEMU arguments in the manifest take precedence over the default ones.
+    let qemu = scheme.qemu.unwrap().finalize(Arch::RiscV64);
+    assert!(qemu.args.contains("-machine q35"));
+    assert_eq!(qemu.path, PathBuf::from("qemu-system-riscv64"));
+
+    // Otherwise, a machine that matches the architecture is selected.
+    let qemu = scheme::QemuScheme::default().finalize(Arch::RiscV64);
+    assert!(qemu.args.contains("-machine virt"));
+    let qemu = scheme::QemuScheme::default().finalize(Arch::Aarch64);
+    assert!(qemu.args.contains("-machine virt"));
+    assert_eq!(qemu.path, PathBuf::from("qemu-system-aarch64"));
+}
+
+#[test]
+fn unknown_key_and_value() {
+    let content = "[boot]\nmethod = \"qemu-direct\"\nprotocl = \"elf\"\n";
+    let err = toml::from_str::<manifest::TomlManifest>(content).unwrap_err();
+    let span = err.span().unwrap();
+    assert_eq!(validate::line_and_column(content, span.start), (3, 1));
+    assert_eq!(
+        validate::suggest_similar(err.message()).as_deref(),
+        Some("a key with a similar name exists: `protocol`")
+    );
+
+    // The keys in nested tables and schemes are checked as well.
+    let content = "[scheme.\"foo\"]\nqemu.arg = \"-m 8G\"\n";
+    let err = toml::from_str::<manifest::TomlManifest>(content).unwrap_err();
+    assert_eq!(
+        validate::suggest_similar(err.message()).as_deref(),
+        Some("a key with a similar name exists: `args`")
+    );
+
+    let content = "[boot]\nmethod = \"qemu-directt\"\n";
+    let err = toml::from_str::<manifest::TomlManifest>(content).unwrap_err();
+    assert_eq!(
+        validate::suggest_similar(err.message()).as_deref(),
+        Some("a value with a similar name exists: `qemu-direct`")
+    );
+
+    // Nothing is suggested if no key is similar enough.
+    let content = "[boot]\nfirmware = \"ovmf\"\n";
+    let err = toml::from_str::<manifest::TomlManifest>(content).unwrap_err();
+    assert_eq!(validate::suggest_similar(err.message()), None);
+}
+
+#[test]
+fn grub_key_aliases() {
+    let content = "[grub]\nmkrescue_path = \"/usr/bin/grub-mkrescue\"\nprotocol = \"linux\"\n";
+    let toml_manifest: manifest::TomlManifest = toml::from_str(content).unwrap();
+    let grub = toml_manifest.default_scheme.grub.unwrap().finalize();
+    assert_eq!(grub.grub_mkrescue, PathBuf::from("/usr/bin/grub-mkrescue"));
+    assert_eq!(grub.boot_protocol, scheme::BootProtocol::Linux);
+}
+
+#[test]
+fn cross_field_checks() {
+    let mut config = Config {
+        work_dir: PathBuf::from("/"),
+        target_arch: Arch::X86_64,
+        build: scheme::Build::default(),
+        run: scheme::Action::default(),
+        test: scheme::Action::default(),
+        debug: scheme::DebugConfig::default(),
+        disk_image: scheme::DiskImage::default(),
+        serial_raw: None,
+    };
+    assert!(validate::check_config(&config).is_empty());
+
+    // Booting with the EFI handover protocol requires a UEFI firmware.
+    config.run.boot.protocol = scheme::DirectBootProtocol::LinuxEfiHandover;
+    config.run.qemu.args = "-machine q35 -m 8G".to_owned();
+    let diagnostics = validate::check_config(&config);
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].is_error());
+
+    config.run.qemu.args = "-machine q35 -m 8G \
+        -drive if=pflash,format=raw,unit=0,readonly=on,file=/root/ovmf/release/OVMF.fd"
+        .to_owned();
+    assert!(validate::check_config(&config).is_empty());
+
+    // The GRUB boot methods are only supported on x86-64.
+    config.target_arch = Arch::RiscV64;
+    config.test.boot.method = scheme::BootMethod::GrubRescueIso;
+    let diagnostics = validate::check_config(&config);
+    assert_eq!(diagnostics.len(), 2);
+    assert!(diagnostics.iter().all(|diagnostic| diagnostic.is_error()));
+
+    // The raw serial port is only supported on x86-64, and it cannot be shared
+    // with the GDB stub.
+    config.target_arch = Arch::X86_64;
+    config.run.boot.protocol = scheme::DirectBootProtocol::Elf;
+    config.test.boot.method = scheme::BootMethod::QemuDirect;
+    config.serial_raw = Some(PathBuf::from("/tmp/serial.bin"));
+    assert!(validate::check_config(&config).is_empty());
+    config.test.boot.kcmdline = vec!["ostd.gdbstub=ttyS1".to_owned()];
+    assert_eq!(validate::check_config(&config).len(), 1);
+    config.target_arch = Arch::RiscV64;
+    assert_eq!(validate::check_config(&config).len(), 2);
+}
+
+#[test]
+fn kcmd_arg_checks() {
+    assert!(validate::is_single_kcmd_arg("syscall.audit"));
+    assert!(validate::is_single_kcmd_arg(
+        "dm-mod.create=\"root,,,ro,0 8 linear /dev/vda 0\""
+    ));
+    assert!(!validate::is_single_kcmd_arg("dm-mod.create=root,,,ro,0 8"));
+    assert!(!validate::is_single_kcmd_arg("dm-mod.create=\"root"));
+    assert!(!validate::is_single_kcmd_arg(""));
+
+    let mut config = Config {
+        work_dir: PathBuf::from("/"),
+        target_arch: Arch::X86_64,
+        build: scheme::Build::default(),
+        run: scheme::Action::default(),
+        test: scheme::Action::default(),
+        debug: scheme::DebugConfig::default(),
+        disk_image: scheme::DiskImage::default(),
+        serial_raw: None,
+    };
+    // The arguments of the init process are not checked.
+    config.run.boot.kcmdline = ["init=/bin/sh", "--", "-c", "echo hello"]
+        .map(str::to_owned)
+        .to_vec();
+    assert!(validate::check_config(&config).is_empty());
+
+    config.run.boot.kcmdline[0] = "init=/bin/sh -l".to_owned();
+    let diagnostics = validate::check_config(&config);
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].is_error());
+}
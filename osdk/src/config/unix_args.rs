@@ -2,21 +2,18 @@
 
 //! This module contains utilities for manipulating common Unix command-line arguments.
 
-use std::process;
-
 use indexmap::{IndexMap, IndexSet};
 
-use crate::{error::Errno, error_msg};
+use crate::{diagnostic::Diagnostic, error::Errno};
 
 /// Split a string of Unix arguments into an array of key-value strings or switches.
 /// Positional arguments are not supported.
 pub fn split_to_kv_array(args: &str) -> Vec<String> {
-    let target = match shlex::split(args) {
-        Some(v) => v,
-        None => {
-            error_msg!("Failed to parse unix args: {:#?}", args);
-            process::exit(Errno::ParseMetadata as _);
-        }
+    let Some(target) = shlex::split(args) else {
+        Diagnostic::error(format!("Failed to parse unix args: {:#?}", args))
+            .with_code(Errno::ParseMetadata)
+            .with_help("check that the quotes are closed and the escapes are valid")
+            .emit_and_exit();
     };
 
     // Join the key value arguments as a single element
@@ -128,19 +125,8 @@ fn infer_multi_value_keys(array: &Vec<String>, separator: &str) -> IndexSet<Stri
 }
 
 pub fn get_key(item: &str, separator: &str) -> Option<String> {
-    let split = item.split(separator).collect::<Vec<_>>();
-    let len = split.len();
-    if len > 2 || len == 0 {
-        error_msg!("`{}` is an invalid argument.", item);
-        process::exit(Errno::ParseMetadata as _);
-    }
-
-    if len == 1 {
-        return None;
-    }
-
-    let key = split.first().unwrap();
-
+    // The value may contain the separator, e.g., `KEY=VALUE=1` or `-append "A B"`.
+    let (key, _) = item.split_once(separator)?;
     Some(key.to_string())
 }
 
@@ -164,6 +150,10 @@ pub mod test {
 
         let string4 = "-device";
         assert!(get_key(string4, " ").is_none());
+
+        let string5 = "rootflags=data=ordered";
+        let key = get_key(string5, "=").unwrap();
+        assert_eq!(key.as_str(), "rootflags");
     }
 
     #[test]
@@ -0,0 +1,231 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Validation of the OSDK manifest and the effective configuration.
+//!
+//! The errors in `OSDK.toml` are reported with their lines and columns, and a key or a value
+//! with a similar name is suggested if an unknown one is found. After the manifest and the
+//! command line arguments are merged, the constraints between the fields are checked on the
+//! effective configuration, so that an inconsistent configuration is rejected before building.
+
+use std::path::Path;
+
+use linux_bzimage_builder::PayloadEncoding;
+use regex::Regex;
+
+use super::{
+    scheme::{firmware_files, Action, BootMethod, BootProtocol, DirectBootProtocol},
+    Config,
+};
+use crate::{
+    arch::Arch,
+    diagnostic::{Diagnostic, Location},
+    error::Errno,
+};
+
+/// Reports the error in deserializing the manifest and exits.
+pub fn exit_on_toml_error(path: &Path, contents: &str, err: &toml::de::Error) -> ! {
+    let mut diagnostic = Diagnostic::error(format!(
+        "Cannot parse `{}`: {}",
+        path.display(),
+        err.message()
+    ))
+    .with_code(Errno::ParseMetadata);
+    if let Some(span) = err.span() {
+        let (line, column) = line_and_column(contents, span.start);
+        diagnostic = diagnostic.with_location(Location {
+            file: path.display().to_string(),
+            line,
+            column,
+        });
+    }
+    if let Some(help) = suggest_similar(err.message()) {
+        diagnostic = diagnostic.with_help(help);
+    }
+    diagnostic.emit_and_exit()
+}
+
+/// Returns the line and the column (both starting from 1) of the byte offset.
+pub(super) fn line_and_column(contents: &str, offset: usize) -> (usize, usize) {
+    let before = &contents[..offset.min(contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+/// Suggests a similar key or value for an error about an unknown one.
+///
+/// The error messages of unknown keys and values are generated by `serde`, e.g., "unknown field
+/// `protocl`, expected one of `method`, `protocol`".
+pub(super) fn suggest_similar(message: &str) -> Option<String> {
+    let unknown = Regex::new(r"^unknown (field|variant) `([^`]*)`, expected (.*)$").unwrap();
+    let captures = unknown.captures(message)?;
+    let kind = if &captures[1] == "field" {
+        "key"
+    } else {
+        "value"
+    };
+
+    let quoted = Regex::new(r"`([^`]*)`").unwrap();
+    let candidates = quoted
+        .captures_iter(&captures[3])
+        .map(|candidate| candidate.get(1).unwrap().as_str());
+    let similar = most_similar(&captures[2], candidates)?;
+    Some(format!(
+        "a {} with a similar name exists: `{}`",
+        kind, similar
+    ))
+}
+
+/// Returns the candidate that is the most similar to the name, if it is similar enough.
+fn most_similar<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Returns the Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut distances: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = distances[0];
+        distances[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_char != *b_char);
+            diagonal = distances[j + 1];
+            distances[j + 1] = substitution.min(distances[j] + 1).min(diagonal + 1);
+        }
+    }
+    distances[b.len()]
+}
+
+/// Checks the constraints between the fields of the effective configuration.
+pub fn check_config(config: &Config) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    check_action("run", &config.run, config.target_arch, &mut diagnostics);
+    check_action("test", &config.test, config.target_arch, &mut diagnostics);
+    if config.serial_raw.is_some() {
+        check_serial_raw(config, &mut diagnostics);
+    }
+    diagnostics
+}
+
+fn check_serial_raw(config: &Config, diagnostics: &mut Vec<Diagnostic>) {
+    if config.target_arch != Arch::X86_64 {
+        diagnostics.push(
+            Diagnostic::error(format!(
+                "`--serial-raw` is not supported on the architecture `{}`",
+                config.target_arch
+            ))
+            .with_code(Errno::Cli),
+        );
+    }
+
+    for (name, action) in [("run", &config.run), ("test", &config.test)] {
+        let mut kcmdline = action.boot.kcmdline.iter().take_while(|arg| *arg != "--");
+        if kcmdline.any(|arg| arg == "ostd.gdbstub=ttyS1") {
+            diagnostics.push(
+                Diagnostic::error(format!(
+                    "`--serial-raw` uses the serial port `ttyS1`, which is dedicated to the GDB stub by `{}`",
+                    name
+                ))
+                .with_code(Errno::Cli)
+                .with_help("use `ostd.gdbstub=ttyS2` instead"),
+            );
+        }
+    }
+}
+
+fn check_action(name: &str, action: &Action, target_arch: Arch, diagnostics: &mut Vec<Diagnostic>) {
+    let boot = &action.boot;
+
+    if action.grub.boot_protocol != BootProtocol::Linux
+        && !boot.protocol.is_linux()
+        && action.build.encoding != PayloadEncoding::Raw
+    {
+        diagnostics.push(
+            Diagnostic::error(format!(
+                "The encoding format of `{}` is specified, but the kernel is not booted with a Linux boot protocol",
+                name
+            ))
+            .with_code(Errno::Cli)
+            .with_help("set `grub.boot_protocol` to `linux` or `boot.protocol` to a Linux one, or remove `build.encoding`"),
+        );
+    }
+
+    if boot.method == BootMethod::QemuDirect
+        && boot.protocol.is_linux()
+        && target_arch != Arch::X86_64
+    {
+        diagnostics.push(
+            Diagnostic::error(format!(
+                "The direct boot protocol `{:?}` of `{}` is not supported on the architecture `{}`",
+                boot.protocol, name, target_arch
+            ))
+            .with_code(Errno::Cli),
+        );
+    }
+    if boot.method != BootMethod::QemuDirect && target_arch != Arch::X86_64 {
+        diagnostics.push(
+            Diagnostic::error(format!(
+                "The boot method `{:?}` of `{}` is not supported on the architecture `{}`",
+                boot.method, name, target_arch
+            ))
+            .with_code(Errno::Cli)
+            .with_help("set `boot.method` to `qemu-direct`"),
+        );
+    }
+
+    if boot.method == BootMethod::QemuDirect
+        && boot.protocol == DirectBootProtocol::LinuxEfiHandover
+        && firmware_files(&action.qemu.args).is_empty()
+    {
+        diagnostics.push(
+            Diagnostic::error(format!(
+                "The direct boot protocol `linux-efi-handover` of `{}` requires a UEFI firmware, but none is given in the QEMU arguments",
+                name
+            ))
+            .with_code(Errno::Cli)
+            .with_help("add OVMF to `qemu.args`, e.g., `-drive if=pflash,format=raw,unit=0,readonly=on,file=/root/ovmf/release/OVMF.fd`"),
+        );
+    }
+
+    // The kernel splits its command line at the spaces outside double quotes, and the arguments
+    // after `--` are for the init process.
+    for arg in boot.kcmdline.iter().take_while(|arg| *arg != "--") {
+        if !is_single_kcmd_arg(arg) {
+            diagnostics.push(
+                Diagnostic::error(format!(
+                    "The kernel command-line argument `{}` of `{}` is not parsed as a single argument by the kernel",
+                    arg, name
+                ))
+                .with_code(Errno::Cli)
+                .with_help("quote the value with double quotes, e.g., `dm-mod.create=\"...\"`"),
+            );
+        }
+    }
+
+    if boot.method == BootMethod::QemuDirect && action.qemu.bootdev_append_options.is_some() {
+        diagnostics.push(Diagnostic::warning(format!(
+            "`qemu.bootdev_append_options` of `{}` has no effect with the boot method `qemu-direct`",
+            name
+        )));
+    }
+}
+
+/// Returns whether the kernel parses the argument as a single one.
+pub(super) fn is_single_kcmd_arg(arg: &str) -> bool {
+    let mut inside_quotes = false;
+    for c in arg.chars() {
+        if c == '"' {
+            inside_quotes = !inside_quotes;
+        } else if c.is_whitespace() && !inside_quotes {
+            return false;
+        }
+    }
+    !arg.is_empty() && !inside_quotes
+}
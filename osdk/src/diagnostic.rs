@@ -0,0 +1,274 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The diagnostic messages reported by OSDK.
+//!
+//! A diagnostic has a severity, an optional error code that matches [`Errno`], an optional
+//! location in a file, and an optional suggestion about how to fix the problem. Diagnostics are printed to the
+//! standard error either as colored human-readable text or as JSON lines, which can be
+//! consumed by editors or CI systems (see `--message-format`).
+
+use std::{
+    fmt::Display,
+    io::{self, IsTerminal, Write},
+    process,
+    sync::OnceLock,
+};
+
+use clap::ValueEnum;
+
+use crate::error::Errno;
+
+/// The format of the diagnostic messages.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, ValueEnum)]
+pub enum MessageFormat {
+    /// Human-readable text, which is colored if the standard error is a terminal.
+    #[default]
+    Human,
+    /// One JSON object per line.
+    Json,
+}
+
+static MESSAGE_FORMAT: OnceLock<MessageFormat> = OnceLock::new();
+
+/// Sets the format of the diagnostic messages.
+///
+/// The format can only be set once. Later calls are ignored.
+pub fn set_message_format(format: MessageFormat) {
+    let _ = MESSAGE_FORMAT.set(format);
+}
+
+fn message_format() -> MessageFormat {
+    MESSAGE_FORMAT.get().copied().unwrap_or_default()
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    severity: Severity,
+    code: Option<String>,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<Location>,
+    help: Option<String>,
+    #[serde(skip)]
+    errno: Option<Errno>,
+}
+
+/// A position in a file, where the line and the column start from 1.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message.into())
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message.into())
+    }
+
+    fn new(severity: Severity, message: String) -> Self {
+        Self {
+            severity,
+            code: None,
+            message,
+            location: None,
+            help: None,
+            errno: None,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Attaches the error code.
+    pub fn with_code(self, errno: Errno) -> Self {
+        Self {
+            code: Some(errno.code()),
+            errno: Some(errno),
+            ..self
+        }
+    }
+
+    /// Attaches the location where the problem is found.
+    pub fn with_location(self, location: Location) -> Self {
+        Self {
+            location: Some(location),
+            ..self
+        }
+    }
+
+    /// Attaches a suggestion about how to fix the problem.
+    pub fn with_help(self, help: impl Into<String>) -> Self {
+        Self {
+            help: Some(help.into()),
+            ..self
+        }
+    }
+
+    /// Prints the diagnostic to the standard error.
+    pub fn emit(&self) {
+        let output = match message_format() {
+            MessageFormat::Human => self.render_human(use_color()),
+            MessageFormat::Json => serde_json::to_string(self).unwrap(),
+        };
+        let _ = writeln!(std::io::stderr(), "{}", output);
+    }
+
+    /// Prints the diagnostic and exits with the error code.
+    ///
+    /// If there is no error code, OSDK exits with 1.
+    pub fn emit_and_exit(&self) -> ! {
+        self.emit();
+        process::exit(self.errno.map_or(1, |errno| errno as _));
+    }
+
+    fn render_human(&self, color: bool) -> String {
+        let (label, color_code) = match self.severity {
+            Severity::Error => ("error", "31"),
+            Severity::Warning => ("warning", "33"),
+        };
+        let title = match &self.code {
+            Some(code) => format!("{}[{}]", label, code),
+            None => label.to_owned(),
+        };
+
+        let mut output = if color {
+            format!(
+                "\x1b[1;{}m{}\x1b[0m\x1b[1m: {}\x1b[0m",
+                color_code, title, self.message
+            )
+        } else {
+            format!("{}: {}", title, self.message)
+        };
+        if let Some(location) = &self.location {
+            let arrow = if color { "\x1b[1;34m-->\x1b[0m" } else { "-->" };
+            output.push_str(&format!(
+                "\n  {} {}:{}:{}",
+                arrow, location.file, location.line, location.column
+            ));
+        }
+        if let Some(help) = &self.help {
+            let label = if color {
+                "\x1b[1;36mhelp\x1b[0m"
+            } else {
+                "help"
+            };
+            output.push_str(&format!("\n  = {}: {}", label, help));
+        }
+        output
+    }
+}
+
+/// Prints the diagnostics and exits with the error code of the first error, if any.
+pub fn emit_all(diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        diagnostic.emit();
+    }
+    if let Some(error) = diagnostics.iter().find(|diagnostic| diagnostic.is_error()) {
+        process::exit(error.errno.map_or(1, |errno| errno as _));
+    }
+}
+
+/// Reports that a host program cannot be launched and exits.
+///
+/// If the program is not found, `help` is suggested to fix the problem.
+pub fn exit_on_launch_failure(program: &dyn Display, err: io::Error, help: &str) -> ! {
+    let diagnostic = Diagnostic::error(format!("Failed to launch `{}`: {}", program, err))
+        .with_code(Errno::ExecuteCommand);
+    if err.kind() == io::ErrorKind::NotFound {
+        diagnostic.with_help(help).emit_and_exit()
+    } else {
+        diagnostic.emit_and_exit()
+    }
+}
+
+fn use_color() -> bool {
+    std::env::var_os("NO_COLOR").is_none() && std::io::stderr().is_terminal()
+}
+
+/// Print error message to console
+#[macro_export]
+macro_rules! error_msg {
+    () => {
+        std::eprint!("")
+    };
+    ($($arg:tt)*) => {{
+        $crate::diagnostic::Diagnostic::error(std::format!($($arg)*)).emit()
+    }};
+}
+
+/// Print warning message to console
+#[macro_export]
+macro_rules! warn_msg {
+    () => {
+        std::eprint!("")
+    };
+    ($($arg:tt)*) => {{
+        $crate::diagnostic::Diagnostic::warning(std::format!($($arg)*)).emit()
+    }};
+}
+
+/// Print error message with the error code to console and exit
+#[macro_export]
+macro_rules! exit_with_error {
+    ($errno:expr, $($arg:tt)*) => {{
+        $crate::diagnostic::Diagnostic::error(std::format!($($arg)*))
+            .with_code($errno)
+            .emit_and_exit()
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render_human() {
+        let diagnostic = Diagnostic::error("qemu not found")
+            .with_code(Errno::ExecuteCommand)
+            .with_help("install qemu-system-x86 or set `qemu.path`");
+        assert_eq!(
+            diagnostic.render_human(false),
+            "error[E0006]: qemu not found\n  = help: install qemu-system-x86 or set `qemu.path`"
+        );
+
+        let diagnostic = Diagnostic::warning("the kernel is not stripped");
+        assert_eq!(
+            diagnostic.render_human(false),
+            "warning: the kernel is not stripped"
+        );
+
+        let diagnostic = Diagnostic::error("unknown field `protocl`")
+            .with_location(Location {
+                file: "OSDK.toml".to_owned(),
+                line: 3,
+                column: 1,
+            })
+            .with_help("a similar field exists: `protocol`");
+        assert_eq!(
+            diagnostic.render_human(false),
+            "error: unknown field `protocl`\n  --> OSDK.toml:3:1\n  = help: a similar field exists: `protocol`"
+        );
+    }
+
+    #[test]
+    fn render_json() {
+        let diagnostic = Diagnostic::error("Cargo build failed").with_code(Errno::ExecuteCommand);
+        assert_eq!(
+            serde_json::to_string(&diagnostic).unwrap(),
+            r#"{"severity":"error","code":"E0006","message":"Cargo build failed","help":null}"#
+        );
+    }
+}
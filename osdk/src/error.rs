@@ -14,30 +14,13 @@ pub enum Errno {
     BadCrateName = 9,
     NoKernelCrate = 10,
     TooManyCrates = 11,
+    MissingComponent = 12,
+    KernelPanic = 13,
 }
 
-/// Print error message to console
-#[macro_export]
-macro_rules! error_msg {
-    () => {
-        std::eprint!("")
-    };
-    ($($arg:tt)*) => {{
-        std::eprint!("[Error]: ");
-        std::eprint!($($arg)*);
-        std::eprint!("\n")
-    }};
-}
-
-/// Print warning message to console
-#[macro_export]
-macro_rules! warn_msg {
-    () => {
-        std::eprint!("")
-    };
-    ($($arg:tt)*) => {{
-        std::eprint!("[Warn]: ");
-        std::eprint!($($arg)*);
-        std::eprint!("\n")
-    }};
+impl Errno {
+    /// Returns the error code shown in the diagnostic messages, e.g., `E0008`.
+    pub fn code(self) -> String {
+        format!("E{:04}", self as i32)
+    }
 }
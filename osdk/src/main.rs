@@ -13,6 +13,7 @@ mod bundle;
 mod cli;
 mod commands;
 mod config;
+mod diagnostic;
 mod error;
 mod util;
 
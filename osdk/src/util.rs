@@ -4,13 +4,13 @@ use std::{
     collections::HashMap,
     ffi::OsStr,
     fs::{self, File},
-    io::{BufRead, BufReader, Result, Write},
+    io::{BufRead, BufReader, Read, Result, Seek, SeekFrom, Write},
     path::{Path, PathBuf},
     process::Command,
     sync::{LazyLock, Mutex},
 };
 
-use crate::{error::Errno, error_msg};
+use crate::{error::Errno, exit_with_error};
 
 use quote::ToTokens;
 
@@ -32,8 +32,7 @@ pub fn cargo_new_lib(crate_name: &str) {
     command.args(["new", "--lib", crate_name]);
     let status = command.status().unwrap();
     if !status.success() {
-        error_msg!("Failed to create new crate");
-        std::process::exit(Errno::CreateCrate as _);
+        exit_with_error!(Errno::CreateCrate, "Failed to create new crate");
     }
 }
 
@@ -155,11 +154,15 @@ pub fn get_kernel_crate() -> CrateInfo {
     if kernel_crates.len() == 1 {
         kernel_crates[0].clone()
     } else if kernel_crates.is_empty() {
-        error_msg!("No kernel crate found in the current workspace");
-        std::process::exit(Errno::NoKernelCrate as _);
+        exit_with_error!(
+            Errno::NoKernelCrate,
+            "No kernel crate found in the current workspace"
+        );
     } else {
-        error_msg!("Multiple kernel crates found in the current workspace");
-        std::process::exit(Errno::TooManyCrates as _);
+        exit_with_error!(
+            Errno::TooManyCrates,
+            "Multiple kernel crates found in the current workspace"
+        );
     }
 }
 
@@ -250,14 +253,24 @@ fn parse_package_id_string(package_id: &str) -> ParsedID {
     }
 }
 
+/// The maximum size of the end of the QEMU log that is searched for a stack trace.
+const PANIC_LOG_TAIL_SIZE: u64 = 256 * 1024;
+
 /// Print source line stack trace if a panic is detected from QEMU log.
 ///
 /// The source line is produced with the `addr2line` command using the PC values in the panic
 /// stack trace.
-pub fn trace_panic_from_log(qemu_log: File, bin_path: PathBuf) {
-    // We read last 500 lines since more than 100 layers of stack trace is unlikely.
-    let reader = rev_buf_reader::RevBufReader::new(qemu_log);
-    let lines: Vec<String> = reader.lines().take(500).map(|l| l.unwrap()).collect();
+pub fn trace_panic_from_log(mut qemu_log: File, bin_path: PathBuf) {
+    // We read last 500 lines since more than 100 layers of stack trace is unlikely. The log is not
+    // necessarily valid UTF-8 because it contains whatever the guest prints, so it is decoded
+    // lossily.
+    let len = qemu_log.metadata().map_or(0, |metadata| metadata.len());
+    let mut tail = Vec::new();
+    let _ = qemu_log
+        .seek(SeekFrom::Start(len.saturating_sub(PANIC_LOG_TAIL_SIZE)))
+        .and_then(|_| qemu_log.read_to_end(&mut tail));
+    let tail = String::from_utf8_lossy(&tail);
+    let lines: Vec<&str> = tail.lines().rev().take(500).collect();
     let mut trace_exists = false;
     let mut stack_num = 0;
     let pc_matcher = regex::Regex::new(r" - pc (0x[0-9a-fA-F]+)").unwrap();
@@ -275,7 +288,7 @@ pub fn trace_panic_from_log(qemu_log: File, bin_path: PathBuf) {
             trace_exists = true;
         }
         if trace_exists {
-            if let Some(cap) = pc_matcher.captures(&line) {
+            if let Some(cap) = pc_matcher.captures(line) {
                 let pc = cap.get(1).unwrap().as_str();
                 let mut stdin = addr2line_proc.stdin.as_ref().unwrap();
                 stdin.write_all(pc.as_bytes()).unwrap();
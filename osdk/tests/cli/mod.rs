@@ -39,6 +39,62 @@ fn cli_test_help_message() {
     assert_stdout_contains_msg(&output, "cargo osdk test [OPTIONS] [TESTNAME]");
 }
 
+#[test]
+fn cli_fuzz_help_message() {
+    let output = cargo_osdk(&["fuzz", "-h"]).output().unwrap();
+    assert_success(&output);
+    assert_stdout_contains_msg(&output, "cargo osdk fuzz [OPTIONS]");
+}
+
+#[test]
+fn cli_crash_test_help_message() {
+    let output = cargo_osdk(&["crash-test", "-h"]).output().unwrap();
+    assert_success(&output);
+    assert_stdout_contains_msg(&output, "cargo osdk crash-test [OPTIONS]");
+}
+
+#[test]
+fn cli_deploy_help_message() {
+    let output = cargo_osdk(&["deploy", "-h"]).output().unwrap();
+    assert_success(&output);
+    assert_stdout_contains_msg(&output, "cargo osdk deploy [OPTIONS]");
+}
+
+#[test]
+fn cli_burn_help_message() {
+    let output = cargo_osdk(&["burn", "-h"]).output().unwrap();
+    assert_success(&output);
+    assert_stdout_contains_msg(&output, "cargo osdk burn [OPTIONS]");
+}
+
+#[test]
+fn cli_sbom_help_message() {
+    let output = cargo_osdk(&["sbom", "-h"]).output().unwrap();
+    assert_success(&output);
+    assert_stdout_contains_msg(&output, "cargo osdk sbom [OPTIONS]");
+}
+
+#[test]
+fn cli_measure_help_message() {
+    let output = cargo_osdk(&["measure", "-h"]).output().unwrap();
+    assert_success(&output);
+    assert_stdout_contains_msg(&output, "cargo osdk measure [OPTIONS]");
+}
+
+#[test]
+fn cli_metrics_help_message() {
+    let output = cargo_osdk(&["metrics", "compare", "-h"]).output().unwrap();
+    assert_success(&output);
+    assert_stdout_contains_msg(&output, "cargo osdk metrics compare [OPTIONS] <BASE> <NEW>");
+}
+
+#[test]
+fn cli_check_config_help_message() {
+    let output = cargo_osdk(&["check-config", "-h"]).output().unwrap();
+    assert_success(&output);
+    assert_stdout_contains_msg(&output, "cargo osdk check-config [OPTIONS]");
+}
+
 #[test]
 fn cli_check_help_message() {
     let output = cargo_osdk(&["check", "-h"]).output().unwrap();
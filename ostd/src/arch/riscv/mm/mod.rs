@@ -243,3 +243,19 @@ pub(crate) fn __memset_fallible(dst: *mut u8, value: u8, size: usize) -> usize {
     unsafe { core::ptr::write_bytes(dst, value, size) };
     0
 }
+
+pub(crate) fn __atomic_cmpxchg_fallible(ptr: *mut u32, old_val: u32, new_val: u32) -> Option<u32> {
+    // TODO: implement fallible
+    unsafe {
+        riscv::register::sstatus::set_sum();
+    }
+    let atomic = unsafe { core::sync::atomic::AtomicU32::from_ptr(ptr) };
+    match atomic.compare_exchange(
+        old_val,
+        new_val,
+        core::sync::atomic::Ordering::AcqRel,
+        core::sync::atomic::Ordering::Acquire,
+    ) {
+        Ok(prev_val) | Err(prev_val) => Some(prev_val),
+    }
+}
@@ -9,6 +9,7 @@ pub mod iommu;
 pub(crate) mod irq;
 pub(crate) mod mm;
 pub(crate) mod pci;
+pub(crate) mod power;
 pub mod qemu;
 pub mod serial;
 pub mod task;
@@ -59,6 +60,14 @@ pub fn read_random() -> Option<u64> {
     None
 }
 
+/// Reads a 64-bit random value from the hardware entropy source directly.
+///
+/// Returns None if no random value was generated.
+pub fn read_random_seed() -> Option<u64> {
+    // FIXME: Implement the entropy source with the `seed` CSR of the Zkr extension.
+    None
+}
+
 pub(crate) fn enable_cpu_features() {
     unsafe {
         // We adopt a lazy approach to enable the floating-point unit; it's not
@@ -0,0 +1,29 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Power management via the SBI system reset extension.
+
+use crate::power::ExitCode;
+
+pub(crate) fn poweroff(code: ExitCode) -> ! {
+    match code {
+        ExitCode::Success => sbi_rt::system_reset(sbi_rt::Shutdown, sbi_rt::NoReason),
+        ExitCode::Failure => sbi_rt::system_reset(sbi_rt::Shutdown, sbi_rt::SystemFailure),
+    };
+
+    halt_forever()
+}
+
+pub(crate) fn restart() -> ! {
+    sbi_rt::system_reset(sbi_rt::ColdReboot, sbi_rt::NoReason);
+
+    halt_forever()
+}
+
+fn halt_forever() -> ! {
+    log::error!("Failed to reset the system via SBI");
+
+    crate::arch::irq::disable_local();
+    loop {
+        riscv::asm::wfi();
+    }
+}
@@ -20,3 +20,10 @@ pub fn exit_qemu(exit_code: QemuExitCode) -> ! {
     };
     unreachable!("qemu does not exit");
 }
+
+/// Writes the bytes to the QEMU debug console.
+///
+/// Returns `false` since QEMU does not provide a debug console on RISC-V.
+pub(crate) fn write_debugcon(_bytes: &[u8]) -> bool {
+    false
+}
@@ -21,6 +21,16 @@ pub(crate) static TIMEBASE_FREQ: AtomicU64 = AtomicU64::new(1);
 /// [`IoMem`] of goldfish RTC, which will be used by `aster-time`.
 pub static GOLDFISH_IO_MEM: Once<IoMem> = Once::new();
 
+/// Stops the timer interrupts on the current CPU.
+///
+/// Returns `false` since the periodic timer interrupts are not enabled on RISC-V.
+pub(crate) fn stop_local_timer() -> bool {
+    false
+}
+
+/// Restarts the timer interrupts on the current CPU that are stopped by [`stop_local_timer`].
+pub(crate) fn restart_local_timer() {}
+
 pub(super) fn init() {
     let timer_freq = DEVICE_TREE
         .get()
@@ -8,7 +8,7 @@ use spin::Once;
 pub use trap::{GeneralRegs, TrapFrame, UserContext};
 
 use super::cpu::context::CpuExceptionInfo;
-use crate::cpu_local_cell;
+use crate::{cpu_local_cell, trap::handle_fatal_kernel_exception};
 
 cpu_local_cell! {
     static IS_KERNEL_INTERRUPTED: bool = false;
@@ -39,9 +39,9 @@ extern "C" fn trap_handler(f: &mut TrapFrame) {
         }
         Trap::Exception(e) => {
             let stval = riscv::register::stval::read();
-            panic!(
+            handle_fatal_kernel_exception(format_args!(
                 "Cannot handle kernel cpu exception: {e:?}. stval: {stval:#x}, trapframe: {f:#x?}.",
-            );
+            ));
         }
     }
 }
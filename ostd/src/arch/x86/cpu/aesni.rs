@@ -0,0 +1,229 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The AES instructions (AES-NI).
+//!
+//! The instructions operate on the SIMD registers, so they can only be used while a
+//! [`KernelFpuGuard`] is alive. The methods of [`AesNiKey`] take a reference to the guard to
+//! ensure this.
+
+use core::{
+    arch::x86_64::{
+        __m128i, _mm_aesdec_si128, _mm_aesdeclast_si128, _mm_aesenc_si128, _mm_aesenclast_si128,
+        _mm_aesimc_si128, _mm_aeskeygenassist_si128, _mm_loadu_si128, _mm_shuffle_epi32,
+        _mm_slli_si128, _mm_storeu_si128, _mm_xor_si128,
+    },
+    fmt::Debug,
+};
+
+use super::{
+    context::KernelFpuGuard,
+    feature::{has_cpu_feature, CpuFeature},
+};
+
+/// The number of round keys of AES-256, which has the most rounds.
+const MAX_NR_ROUND_KEYS: usize = 15;
+
+type RoundKeys = [[u8; 16]; MAX_NR_ROUND_KEYS];
+
+/// An AES key that is expanded for the AES instructions.
+#[derive(Clone)]
+pub struct AesNiKey {
+    enc_keys: RoundKeys,
+    dec_keys: RoundKeys,
+    nr_rounds: usize,
+}
+
+impl AesNiKey {
+    /// Returns whether the CPU supports the AES instructions.
+    pub fn is_supported() -> bool {
+        has_cpu_feature(CpuFeature::Aesni)
+    }
+
+    /// Expands the 128-bit or 256-bit key.
+    ///
+    /// Returns `None` if the CPU does not support the AES instructions or the length of the key
+    /// is invalid.
+    pub fn new(key: &[u8], _guard: &KernelFpuGuard) -> Option<Self> {
+        if !Self::is_supported() {
+            return None;
+        }
+
+        let mut enc_keys = [[0; 16]; MAX_NR_ROUND_KEYS];
+        let nr_rounds = match key.len() {
+            16 => {
+                // SAFETY: The CPU supports the AES instructions, and the SIMD registers can be
+                // used since the guard is alive.
+                unsafe { expand_key_128(key.try_into().unwrap(), &mut enc_keys) };
+                10
+            }
+            32 => {
+                // SAFETY: Same as above.
+                unsafe { expand_key_256(key.try_into().unwrap(), &mut enc_keys) };
+                14
+            }
+            _ => return None,
+        };
+
+        let mut dec_keys = [[0; 16]; MAX_NR_ROUND_KEYS];
+        // SAFETY: Same as above.
+        unsafe { invert_keys(&enc_keys[..=nr_rounds], &mut dec_keys[..=nr_rounds]) };
+
+        Some(Self {
+            enc_keys,
+            dec_keys,
+            nr_rounds,
+        })
+    }
+
+    /// Encrypts the block in place.
+    pub fn encrypt_block(&self, block: &mut [u8; 16], _guard: &KernelFpuGuard) {
+        // SAFETY: The key can only be created if the CPU supports the AES instructions, and the
+        // SIMD registers can be used since the guard is alive.
+        unsafe { encrypt_block(&self.enc_keys[..=self.nr_rounds], block) };
+    }
+
+    /// Decrypts the block in place.
+    pub fn decrypt_block(&self, block: &mut [u8; 16], _guard: &KernelFpuGuard) {
+        // SAFETY: Same as `encrypt_block`.
+        unsafe { decrypt_block(&self.dec_keys[..=self.nr_rounds], block) };
+    }
+}
+
+impl Debug for AesNiKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        // The round keys are secrets.
+        f.debug_struct("AesNiKey")
+            .field("nr_rounds", &self.nr_rounds)
+            .finish_non_exhaustive()
+    }
+}
+
+#[target_feature(enable = "sse2")]
+unsafe fn load(bytes: &[u8; 16]) -> __m128i {
+    // SAFETY: The pointer is valid for reading 16 bytes, and the unaligned load is used.
+    unsafe { _mm_loadu_si128(bytes.as_ptr().cast()) }
+}
+
+#[target_feature(enable = "sse2")]
+unsafe fn store(value: __m128i, bytes: &mut [u8; 16]) {
+    // SAFETY: The pointer is valid for writing 16 bytes, and the unaligned store is used.
+    unsafe { _mm_storeu_si128(bytes.as_mut_ptr().cast(), value) }
+}
+
+/// Computes the next round key from the previous one and the broadcast word of the output of
+/// `aeskeygenassist`.
+#[target_feature(enable = "sse2")]
+unsafe fn next_round_key(key: __m128i, assist: __m128i) -> __m128i {
+    // SAFETY: The caller ensures that the instructions can be used.
+    unsafe {
+        let key = _mm_xor_si128(key, _mm_slli_si128::<4>(key));
+        let key = _mm_xor_si128(key, _mm_slli_si128::<4>(key));
+        let key = _mm_xor_si128(key, _mm_slli_si128::<4>(key));
+        _mm_xor_si128(key, assist)
+    }
+}
+
+#[target_feature(enable = "aes,sse2")]
+unsafe fn expand_key_128(key: &[u8; 16], round_keys: &mut RoundKeys) {
+    // SAFETY: The caller ensures that the instructions can be used.
+    unsafe {
+        let mut key = load(key);
+        store(key, &mut round_keys[0]);
+
+        macro_rules! expand {
+            ($($index:literal => $rcon:literal),*) => {
+                $(
+                    let assist = _mm_aeskeygenassist_si128::<$rcon>(key);
+                    key = next_round_key(key, _mm_shuffle_epi32::<0xff>(assist));
+                    store(key, &mut round_keys[$index]);
+                )*
+            };
+        }
+        expand!(
+            1 => 0x01, 2 => 0x02, 3 => 0x04, 4 => 0x08, 5 => 0x10,
+            6 => 0x20, 7 => 0x40, 8 => 0x80, 9 => 0x1b, 10 => 0x36
+        );
+    }
+}
+
+#[target_feature(enable = "aes,sse2")]
+unsafe fn expand_key_256(key: &[u8; 32], round_keys: &mut RoundKeys) {
+    // SAFETY: The caller ensures that the instructions can be used.
+    unsafe {
+        let mut even_key = load(key[..16].try_into().unwrap());
+        let mut odd_key = load(key[16..].try_into().unwrap());
+        store(even_key, &mut round_keys[0]);
+        store(odd_key, &mut round_keys[1]);
+
+        macro_rules! expand_even {
+            ($index:literal => $rcon:literal) => {
+                let assist = _mm_aeskeygenassist_si128::<$rcon>(odd_key);
+                even_key = next_round_key(even_key, _mm_shuffle_epi32::<0xff>(assist));
+                store(even_key, &mut round_keys[$index]);
+            };
+        }
+        macro_rules! expand_odd {
+            ($index:literal) => {
+                let assist = _mm_aeskeygenassist_si128::<0>(even_key);
+                odd_key = next_round_key(odd_key, _mm_shuffle_epi32::<0xaa>(assist));
+                store(odd_key, &mut round_keys[$index]);
+            };
+        }
+        expand_even!(2 => 0x01);
+        expand_odd!(3);
+        expand_even!(4 => 0x02);
+        expand_odd!(5);
+        expand_even!(6 => 0x04);
+        expand_odd!(7);
+        expand_even!(8 => 0x08);
+        expand_odd!(9);
+        expand_even!(10 => 0x10);
+        expand_odd!(11);
+        expand_even!(12 => 0x20);
+        expand_odd!(13);
+        expand_even!(14 => 0x40);
+    }
+}
+
+/// Computes the round keys for the equivalent inverse cipher used by `aesdec`.
+#[target_feature(enable = "aes,sse2")]
+unsafe fn invert_keys(enc_keys: &[[u8; 16]], dec_keys: &mut [[u8; 16]]) {
+    let last = enc_keys.len() - 1;
+    dec_keys[0] = enc_keys[last];
+    dec_keys[last] = enc_keys[0];
+    for i in 1..last {
+        // SAFETY: The caller ensures that the instructions can be used.
+        unsafe {
+            store(
+                _mm_aesimc_si128(load(&enc_keys[last - i])),
+                &mut dec_keys[i],
+            )
+        };
+    }
+}
+
+#[target_feature(enable = "aes,sse2")]
+unsafe fn encrypt_block(round_keys: &[[u8; 16]], block: &mut [u8; 16]) {
+    let (last_key, keys) = round_keys.split_last().unwrap();
+    // SAFETY: The caller ensures that the instructions can be used.
+    unsafe {
+        let mut state = _mm_xor_si128(load(block), load(&keys[0]));
+        for key in keys[1..].iter() {
+            state = _mm_aesenc_si128(state, load(key));
+        }
+        store(_mm_aesenclast_si128(state, load(last_key)), block);
+    }
+}
+
+#[target_feature(enable = "aes,sse2")]
+unsafe fn decrypt_block(round_keys: &[[u8; 16]], block: &mut [u8; 16]) {
+    let (last_key, keys) = round_keys.split_last().unwrap();
+    // SAFETY: The caller ensures that the instructions can be used.
+    unsafe {
+        let mut state = _mm_xor_si128(load(block), load(&keys[0]));
+        for key in keys[1..].iter() {
+            state = _mm_aesdec_si128(state, load(key));
+        }
+        store(_mm_aesdeclast_si128(state, load(last_key)), block);
+    }
+}
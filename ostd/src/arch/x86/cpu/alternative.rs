@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The alternative instructions.
+//!
+//! An alternative instruction is a piece of code that is patched at boot time with a replacement
+//! if the CPU supports a feature, so that the code can use the feature without checking it at
+//! runtime. Each alternative instruction is recorded as an [`AltInstr`] in the
+//! `.altinstructions` section, while the replacement is placed in the
+//! `.text.altinstr_replacement` section.
+//!
+//! In Rust, use the [`alternative`] macro. In assembly, add the following statements, where
+//! `FEATURE` is the index of a [`CpuFeature`]:
+//!
+//! ```text
+//! 661:
+//!     <the original instructions>
+//! 662:
+//! .pushsection .altinstructions, "a"
+//!     .balign 8
+//!     .quad 661b
+//!     .quad 663f
+//!     .2byte FEATURE
+//!     .byte 662b - 661b
+//!     .byte 664f - 663f
+//!     .4byte 0
+//! .popsection
+//! .pushsection .text.altinstr_replacement, "ax"
+//! 663:
+//!     <the replacement instructions>
+//! 664:
+//! .popsection
+//! ```
+//!
+//! The replacement must not be longer than the original instructions, and the rest of the
+//! original instructions are filled with `nop`s. The replacement is copied as is, so it must not
+//! contain any instruction that is relative to the instruction pointer.
+//!
+//! [`CpuFeature`]: super::feature::CpuFeature
+
+use core::mem::size_of;
+
+use super::feature::has_cpu_feature_index;
+use crate::{
+    const_assert,
+    mm::{kspace::kernel_loaded_offset, paddr_to_vaddr, Vaddr},
+};
+
+/// An entry in the `.altinstructions` section.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct AltInstr {
+    /// The address of the original instructions.
+    orig_addr: Vaddr,
+    /// The address of the replacement instructions.
+    repl_addr: Vaddr,
+    /// The index of the required CPU feature.
+    feature: u16,
+    /// The length of the original instructions.
+    orig_len: u8,
+    /// The length of the replacement instructions.
+    repl_len: u8,
+    _padding: u32,
+}
+
+const_assert!(size_of::<AltInstr>() == 24);
+
+/// The one-byte `nop` instruction.
+const NOP: u8 = 0x90;
+
+/// Generates an inline assembly block with an alternative instruction.
+///
+/// The first argument is the required [`CpuFeature`], followed by the original instructions and
+/// the replacement instructions as string literals. The rest of the arguments are passed to
+/// [`core::arch::asm`], except that the operands must be either named or explicit registers,
+/// and the name `alt_feature` is reserved.
+///
+/// [`CpuFeature`]: super::feature::CpuFeature
+macro_rules! alternative {
+    ($feature:expr, $orig:literal, $repl:literal $(, $($args:tt)*)?) => {
+        core::arch::asm!(
+            concat!(
+                "661:\n",
+                $orig, "\n",
+                "662:\n",
+                ".pushsection .altinstructions, \"a\"\n",
+                ".balign 8\n",
+                ".quad 661b\n",
+                ".quad 663f\n",
+                ".2byte {alt_feature}\n",
+                ".byte 662b - 661b\n",
+                ".byte 664f - 663f\n",
+                ".4byte 0\n",
+                ".popsection\n",
+                ".pushsection .text.altinstr_replacement, \"ax\"\n",
+                "663:\n",
+                $repl, "\n",
+                "664:\n",
+                ".popsection\n",
+            ),
+            alt_feature = const $feature as u16
+            $(, $($args)*)?
+        )
+    };
+}
+
+pub(crate) use alternative;
+
+/// Patches the alternative instructions whose features are supported by the CPU.
+///
+/// # Safety
+///
+/// This function must be called only once on the BSP, with the local IRQs disabled, after the
+/// CPU features are detected and before the APs are booted. No alternative instruction can be
+/// executing while it is being patched.
+pub(crate) unsafe fn apply_alternatives() {
+    extern "C" {
+        fn __alt_instructions();
+        fn __alt_instructions_end();
+    }
+
+    let start = __alt_instructions as usize;
+    let end = __alt_instructions_end as usize;
+    let nr_entries = (end - start) / size_of::<AltInstr>();
+    // SAFETY: The linker script places the entries between the two symbols.
+    let entries = unsafe { core::slice::from_raw_parts(start as *const AltInstr, nr_entries) };
+
+    for entry in entries {
+        if !has_cpu_feature_index(entry.feature) {
+            continue;
+        }
+        assert!(
+            entry.repl_len <= entry.orig_len,
+            "the replacement of the alternative instruction at {:#x} is too long",
+            entry.orig_addr
+        );
+
+        // The kernel code is not writable, so it is patched via the linear mapping.
+        let orig = paddr_to_vaddr(entry.orig_addr - kernel_loaded_offset()) as *mut u8;
+        let repl = entry.repl_addr as *const u8;
+        let (orig_len, repl_len) = (entry.orig_len as usize, entry.repl_len as usize);
+        // SAFETY: The original instructions are in the kernel code and are not executing (as
+        // upheld by the caller). The replacement is valid for reads of `repl_len` bytes.
+        unsafe {
+            core::ptr::copy_nonoverlapping(repl, orig, repl_len);
+            core::ptr::write_bytes(orig.add(repl_len), NOP, orig_len - repl_len);
+        }
+    }
+
+    // Serialize the instruction stream, so that the patched code is fetched again.
+    // SAFETY: CPUID has no side effects.
+    let _ = unsafe { core::arch::x86_64::__cpuid(0) };
+}
@@ -0,0 +1,655 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The FPU and SIMD states.
+//!
+//! The FPU state of a user task includes the x87 FPU, SSE, AVX, and AVX-512 registers. It is
+//! saved in an XSAVE area, whose size depends on the features enabled in `XCR0` and is queried
+//! with CPUID at boot time. If the CPU supports `XSAVES` (or `XSAVEOPT`), the state components
+//! that are in their initial configuration, or that have not been modified since the state was
+//! last restored, are not written to memory.
+//!
+//! The FPU state of a user task is saved when the task is switched out, but it is restored
+//! lazily, i.e., only when the task is about to return to the user mode and the FPU registers of
+//! the CPU do not hold its state. So switching to a kernel task and then back does not restore
+//! the state. The FPU registers of a CPU hold a state if the state's ID is in [`FPU_OWNER`] and
+//! the state was last loaded on the CPU. While the FPU registers hold the state of the current
+//! task, the saved state of the task may be outdated. The saved states of the other tasks are
+//! always up-to-date.
+//!
+//! The kernel code does not use the FPU registers by default. To use them (e.g., for SIMD
+//! accelerated crypto and memory copies), create a [`KernelFpuGuard`].
+
+use alloc::{boxed::Box, vec};
+use core::{
+    arch::x86_64::{
+        __cpuid_count, _fxrstor64, _fxsave64, _xrstor64, _xrstors64, _xsave64, _xsaveopt64,
+        _xsaves64,
+    },
+    cell::UnsafeCell,
+    fmt::Debug,
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
+};
+
+use spin::Once;
+use x86::msr::{rdmsr, wrmsr};
+use x86_64::registers::xcontrol::XCr0;
+
+use crate::{
+    arch::{
+        cpu::feature::{has_cpu_feature, CpuFeature},
+        CPU_FEATURES,
+    },
+    cpu::{current_cpu_racy, CpuId, PinCurrentCpu},
+    cpu_local_cell,
+    task::{disable_preempt, DisabledPreemptGuard, Task},
+    trap::{disable_local, in_interrupt_context},
+    Error, Result,
+};
+
+/// The FPU state of user task.
+///
+/// This could be used for saving both legacy and modern state format.
+pub struct FpuState {
+    area: Box<[UnsafeCell<XSaveUnit>]>,
+    /// The unique ID of the state, which is never reused.
+    id: u64,
+    /// The ID of the CPU on which the state was last loaded, or `u32::MAX` if it has never been
+    /// loaded as the state of a task.
+    last_cpu: AtomicU32,
+}
+
+// SAFETY: The XSAVE area is only accessed with the local IRQs disabled, either by the task that
+// owns it or by the task that creates it.
+unsafe impl Send for FpuState {}
+// SAFETY: See above.
+unsafe impl Sync for FpuState {}
+
+impl FpuState {
+    /// Initializes a new instance.
+    pub fn init() -> Self {
+        let config = FPU_CONFIG.get().unwrap();
+
+        let state = Self::new_uninit(config);
+        // SAFETY: The state is not shared, and the area is large enough for the legacy region
+        // and the XSAVE header (if any), which are at the beginning of the area.
+        unsafe {
+            let fxsave_area = state.area_ptr().cast::<FxSaveArea>();
+            (*fxsave_area).control = 0x37F;
+            (*fxsave_area).mxcsr = 0x1F80;
+
+            if config.instr != SaveInstr::Fxsave {
+                let header = state
+                    .area_ptr()
+                    .add(size_of::<FxSaveArea>())
+                    .cast::<XSaveHeader>();
+                (*header).xstate_bv = config.features;
+                if config.instr == SaveInstr::Xsaves {
+                    (*header).xcomp_bv = XCOMP_BV_COMPACTED_FORMAT | config.features;
+                }
+            }
+        }
+
+        state
+    }
+
+    /// Allocates a zeroed XSAVE area with a new ID.
+    fn new_uninit(config: &FpuConfig) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+        let nr_units = config.area_size.div_ceil(size_of::<XSaveUnit>());
+        let area = (0..nr_units)
+            .map(|_| UnsafeCell::new(XSaveUnit::ZERO))
+            .collect();
+
+        Self {
+            area,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            last_cpu: AtomicU32::new(u32::MAX),
+        }
+    }
+
+    /// Saves the FPU state of the current task into this instance.
+    ///
+    /// If the FPU registers hold the state of the current task, the registers are saved.
+    /// Otherwise, the state has been saved before the registers are taken by others, and it is
+    /// copied into this instance.
+    pub fn save(&self) {
+        let irq_guard = disable_local();
+        let cpu = irq_guard.current_cpu();
+
+        let current_task = Task::current();
+        match current_task_state(current_task.as_deref()) {
+            Some(task_state) if !task_state.is_loaded_on(cpu) => {
+                if !core::ptr::eq(self, task_state) {
+                    self.copy_from(task_state);
+                }
+            }
+            // SAFETY: The local IRQs are disabled, so the area is not accessed concurrently.
+            _ => unsafe { self.save_registers() },
+        }
+    }
+
+    /// Restores the FPU state of the current task from this instance.
+    ///
+    /// The state is loaded into the FPU registers immediately, and it becomes the state of the
+    /// current task.
+    pub fn restore(&self) {
+        let irq_guard = disable_local();
+        let cpu = irq_guard.current_cpu();
+
+        // SAFETY: The local IRQs are disabled, so the area is not accessed concurrently.
+        unsafe { self.restore_registers() };
+
+        let current_task = Task::current();
+        match current_task_state(current_task.as_deref()) {
+            Some(task_state) => task_state.mark_loaded_on(cpu),
+            None => FPU_OWNER.store(0),
+        }
+    }
+
+    /// Returns the size in bytes of the FPU state in the standard format.
+    ///
+    /// The standard format is the non-compacted format of `XSAVE`, or the legacy format of
+    /// `FXSAVE` if `XSAVE` is not supported. The format is used to expose the FPU state to the
+    /// user space (e.g., in the signal frames).
+    pub fn standard_format_size() -> usize {
+        FPU_CONFIG.get().unwrap().standard_size
+    }
+
+    /// Returns the state components that are saved in the standard format.
+    ///
+    /// This is the `XCR0` bits of the user state components, or zero if `XSAVE` is not
+    /// supported.
+    pub fn standard_format_features() -> u64 {
+        FPU_CONFIG.get().unwrap().features
+    }
+
+    /// Saves the FPU state of the current task into `buf` in the standard format.
+    ///
+    /// If the FPU registers do not hold the state of the current task, the state is restored
+    /// into the registers first. If the current task is not a user task, this instance is
+    /// saved instead.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `buf` is shorter than [`Self::standard_format_size`].
+    pub fn save_standard_format(&self, buf: &mut [u8]) {
+        let config = FPU_CONFIG.get().unwrap();
+        let buf = &mut buf[..config.standard_size];
+        let mut area = XSaveUnit::new_area(config.standard_size);
+
+        let irq_guard = disable_local();
+        self.load_for_current_task(irq_guard.current_cpu());
+
+        let area_ptr = area.as_mut_ptr().cast::<u8>();
+        // SAFETY: The area is large enough and properly aligned for the instruction, and the
+        // instruction is supported. Saving the registers does not change them.
+        unsafe {
+            match config.instr {
+                SaveInstr::Xsaves | SaveInstr::Xsaveopt | SaveInstr::Xsave => {
+                    _xsave64(area_ptr, config.features)
+                }
+                SaveInstr::Fxsave => _fxsave64(area_ptr),
+            }
+        }
+        drop(irq_guard);
+
+        // SAFETY: The area is at least `config.standard_size` bytes long.
+        let area_bytes = unsafe { core::slice::from_raw_parts(area_ptr, config.standard_size) };
+        buf.copy_from_slice(area_bytes);
+    }
+
+    /// Restores the FPU state of the current task from `buf` in the standard format.
+    ///
+    /// The values that the CPU cannot load are sanitized: the reserved bits of `MXCSR` and the
+    /// unsupported state components are cleared. If `buf` only contains the legacy region, the
+    /// state components other than the x87 FPU and SSE ones are reset to their initial
+    /// configuration.
+    ///
+    /// Like [`Self::restore`], the state is loaded into the FPU registers immediately, and it
+    /// becomes the state of the current task.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Error::InvalidArgs`] if `buf` is shorter than the legacy region.
+    pub fn restore_standard_format(&self, buf: &[u8]) -> Result<()> {
+        let config = FPU_CONFIG.get().unwrap();
+        if buf.len() < size_of::<FxSaveArea>() {
+            return Err(Error::InvalidArgs);
+        }
+        let len = buf.len().min(config.standard_size);
+        let mut area = XSaveUnit::new_area(config.standard_size);
+        let area_ptr = area.as_mut_ptr().cast::<u8>();
+
+        // SAFETY: The area is at least `config.standard_size` bytes long, so it is large enough
+        // for the copied bytes, the legacy region, and the XSAVE header (if any).
+        unsafe {
+            core::ptr::copy_nonoverlapping(buf.as_ptr(), area_ptr, len);
+
+            let fxsave_area = area_ptr.cast::<FxSaveArea>();
+            (*fxsave_area).mxcsr &= config.mxcsr_mask;
+
+            if config.instr != SaveInstr::Fxsave {
+                let header = area_ptr.add(size_of::<FxSaveArea>()).cast::<XSaveHeader>();
+                if len < size_of::<FxSaveArea>() + size_of::<XSaveHeader>() {
+                    (*header).xstate_bv = XFEATURE_MASK_FP_SSE & config.features;
+                } else {
+                    (*header).xstate_bv &= config.features;
+                }
+                (*header).xcomp_bv = 0;
+                (*header).reserved = [0; 6];
+            }
+        }
+
+        let irq_guard = disable_local();
+        // SAFETY: The area contains a sanitized state in the standard format, and the
+        // instruction is supported. Loading the user FPU state does not affect the kernel, which
+        // does not use the FPU registers.
+        unsafe {
+            match config.instr {
+                SaveInstr::Xsaves | SaveInstr::Xsaveopt | SaveInstr::Xsave => {
+                    _xrstor64(area_ptr, config.features)
+                }
+                SaveInstr::Fxsave => _fxrstor64(area_ptr),
+            }
+        }
+
+        let current_task = Task::current();
+        match current_task_state(current_task.as_deref()) {
+            Some(task_state) => task_state.mark_loaded_on(irq_guard.current_cpu()),
+            None => FPU_OWNER.store(0),
+        }
+
+        Ok(())
+    }
+
+    /// Makes the FPU registers of the CPU hold the state of the current task.
+    ///
+    /// If the current task is not a user task, this instance is loaded instead.
+    fn load_for_current_task(&self, cpu: CpuId) {
+        let current_task = Task::current();
+        match current_task_state(current_task.as_deref()) {
+            Some(task_state) if task_state.is_loaded_on(cpu) => {}
+            Some(task_state) => {
+                // SAFETY: The local IRQs are disabled, so the area is not accessed concurrently.
+                unsafe { task_state.restore_registers() };
+                task_state.mark_loaded_on(cpu);
+            }
+            None => {
+                // SAFETY: The local IRQs are disabled, so the area is not accessed concurrently.
+                unsafe { self.restore_registers() };
+                FPU_OWNER.store(0);
+            }
+        }
+    }
+
+    /// Returns whether the FPU registers of the CPU hold this state.
+    fn is_loaded_on(&self, cpu: CpuId) -> bool {
+        FPU_OWNER.load() == self.id
+            && self.last_cpu.load(Ordering::Relaxed) == cpu.as_usize() as u32
+    }
+
+    /// Marks that the FPU registers of the CPU hold this state.
+    fn mark_loaded_on(&self, cpu: CpuId) {
+        FPU_OWNER.store(self.id);
+        self.last_cpu
+            .store(cpu.as_usize() as u32, Ordering::Relaxed);
+    }
+
+    fn copy_from(&self, other: &Self) {
+        for (unit, other_unit) in self.area.iter().zip(other.area.iter()) {
+            // SAFETY: The local IRQs are disabled, so the areas are not accessed concurrently.
+            unsafe { *unit.get() = *other_unit.get() };
+        }
+    }
+
+    fn area_ptr(&self) -> *mut u8 {
+        UnsafeCell::raw_get(self.area.as_ptr()).cast()
+    }
+
+    /// Saves the FPU registers into the area.
+    ///
+    /// # Safety
+    ///
+    /// The area must not be accessed concurrently.
+    unsafe fn save_registers(&self) {
+        let config = FPU_CONFIG.get().unwrap();
+        let area = self.area_ptr();
+
+        // SAFETY: The area is large enough and properly aligned for the instruction, and the
+        // instruction is supported.
+        unsafe {
+            match config.instr {
+                SaveInstr::Xsaves => _xsaves64(area, config.features),
+                SaveInstr::Xsaveopt => _xsaveopt64(area, config.features),
+                SaveInstr::Xsave => _xsave64(area, config.features),
+                SaveInstr::Fxsave => _fxsave64(area),
+            }
+        }
+    }
+
+    /// Restores the FPU registers from the area.
+    ///
+    /// # Safety
+    ///
+    /// The area must not be accessed concurrently.
+    unsafe fn restore_registers(&self) {
+        let config = FPU_CONFIG.get().unwrap();
+        let area = self.area_ptr().cast_const();
+
+        // SAFETY: The area contains a valid state saved by the same instruction set (or
+        // initialized by `Self::init`), and the instruction is supported. Loading the user FPU
+        // state does not affect the kernel, which does not use the FPU registers.
+        unsafe {
+            match config.instr {
+                SaveInstr::Xsaves => _xrstors64(area, config.features),
+                SaveInstr::Xsaveopt | SaveInstr::Xsave => _xrstor64(area, config.features),
+                SaveInstr::Fxsave => _fxrstor64(area),
+            }
+        }
+    }
+}
+
+impl Clone for FpuState {
+    fn clone(&self) -> Self {
+        let state = Self::new_uninit(FPU_CONFIG.get().unwrap());
+        let _irq_guard = disable_local();
+        state.copy_from(self);
+        state
+    }
+}
+
+impl Default for FpuState {
+    fn default() -> Self {
+        Self::init()
+    }
+}
+
+impl Debug for FpuState {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FpuState")
+            .field("id", &self.id)
+            .field("last_cpu", &self.last_cpu)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns the FPU state of the current task, if it is a user task.
+fn current_task_state(current_task: Option<&Task>) -> Option<&FpuState> {
+    current_task?
+        .user_ctx()
+        .map(|user_ctx| user_ctx.fpu_state())
+}
+
+/// Restores the FPU state of the current task if the FPU registers do not hold it.
+///
+/// This function must be called with the local IRQs disabled before the current task returns to
+/// the user mode, and the local IRQs must be kept disabled until then.
+pub(super) fn restore_for_user_mode() {
+    let cpu = current_cpu_racy();
+
+    let current_task = Task::current();
+    let Some(task_state) = current_task_state(current_task.as_deref()) else {
+        return;
+    };
+    if task_state.is_loaded_on(cpu) {
+        return;
+    }
+
+    // SAFETY: The local IRQs are disabled, so the area is not accessed concurrently.
+    unsafe { task_state.restore_registers() };
+    task_state.mark_loaded_on(cpu);
+}
+
+/// A guard that allows the kernel code to use the FPU and SIMD registers.
+///
+/// The kernel is compiled without the FPU and SIMD instructions, so the code that uses them
+/// should be in functions with `#[target_feature]` (e.g., `#[target_feature(enable = "avx2")]`),
+/// which are called only while the guard is alive. The registers are initialized when the guard
+/// is created, and their values are discarded when the guard is dropped.
+///
+/// The preemption is disabled while the guard is alive.
+pub struct KernelFpuGuard {
+    _preempt_guard: DisabledPreemptGuard,
+}
+
+impl KernelFpuGuard {
+    /// Takes the FPU registers of the current CPU for the kernel code.
+    ///
+    /// If the FPU registers hold the state of the current task, the state is saved and will be
+    /// restored before the task returns to the user mode.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if it is called in the interrupt context, or if another guard is
+    /// alive on the current CPU.
+    pub fn new() -> Self {
+        assert!(
+            !in_interrupt_context(),
+            "the FPU registers cannot be used in the interrupt context"
+        );
+
+        let preempt_guard = disable_preempt();
+        assert!(
+            !IS_KERNEL_FPU_IN_USE.load(),
+            "the FPU registers are already used by the kernel"
+        );
+        IS_KERNEL_FPU_IN_USE.store(true);
+
+        let irq_guard = disable_local();
+        let current_task = Task::current();
+        if let Some(task_state) = current_task_state(current_task.as_deref()) {
+            if task_state.is_loaded_on(irq_guard.current_cpu()) {
+                // SAFETY: The local IRQs are disabled, so the area is not accessed concurrently.
+                unsafe { task_state.save_registers() };
+            }
+        }
+        FPU_OWNER.store(0);
+        drop(irq_guard);
+
+        const MXCSR_DEFAULT: u32 = 0x1F80;
+        // SAFETY: Initializing the FPU registers does not affect the other kernel code, which
+        // does not use them, and the state of the current task has been saved.
+        unsafe {
+            core::arch::asm!(
+                "fninit",
+                "ldmxcsr [{}]",
+                in(reg) &MXCSR_DEFAULT,
+                options(nostack, readonly),
+            );
+        }
+
+        Self {
+            _preempt_guard: preempt_guard,
+        }
+    }
+}
+
+impl Default for KernelFpuGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for KernelFpuGuard {
+    fn drop(&mut self) {
+        IS_KERNEL_FPU_IN_USE.store(false);
+    }
+}
+
+cpu_local_cell! {
+    /// The ID of the FPU state that the FPU registers hold, or zero if they do not hold any.
+    static FPU_OWNER: u64 = 0;
+    /// Whether the FPU registers are used by the kernel code (see [`KernelFpuGuard`]).
+    static IS_KERNEL_FPU_IN_USE: bool = false;
+}
+
+/// The configuration of the FPU states, which is the same on all the CPUs.
+#[derive(Debug)]
+struct FpuConfig {
+    /// The instructions to save and restore the FPU states.
+    instr: SaveInstr,
+    /// The state components to save and restore.
+    features: u64,
+    /// The size in bytes of the XSAVE area.
+    area_size: usize,
+    /// The size in bytes of the XSAVE area in the standard format.
+    standard_size: usize,
+    /// The bits of `MXCSR` that are supported by the CPU.
+    mxcsr_mask: u32,
+}
+
+/// The instructions to save and restore the FPU states.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SaveInstr {
+    /// `XSAVES` and `XRSTORS`, with the compacted format and all the optimizations.
+    Xsaves,
+    /// `XSAVEOPT` and `XRSTOR`, with the standard format and all the optimizations.
+    Xsaveopt,
+    /// `XSAVE` and `XRSTOR`, with the standard format and the init optimization.
+    Xsave,
+    /// `FXSAVE` and `FXRSTOR`, with the legacy format.
+    Fxsave,
+}
+
+static FPU_CONFIG: Once<FpuConfig> = Once::new();
+
+/// Mask features which are restored when returning to user space.
+///
+/// X87 | SSE | AVX | OPMASK | ZMM_HI256 | HI16_ZMM
+const XFEATURE_MASK_USER_RESTORE: u64 = 0b1110_0111;
+
+/// The x87 FPU and SSE state components, which are in the legacy region of the XSAVE area.
+const XFEATURE_MASK_FP_SSE: u64 = 0b11;
+
+/// The bit in `XCOMP_BV` that indicates the compacted format.
+const XCOMP_BV_COMPACTED_FORMAT: u64 = 1 << 63;
+
+/// The MSR of the supervisor state components that are managed by `XSAVES`.
+const IA32_XSS: u32 = 0xda0;
+
+/// Initializes the FPU state management on the current CPU.
+///
+/// This function must be called on each CPU after the state components are enabled in `XCR0`.
+pub(in crate::arch) fn init() {
+    let config = FPU_CONFIG.call_once(|| {
+        const XSTATE_CPUID: u32 = 0x0000000d;
+
+        let mxcsr_mask = query_mxcsr_mask();
+
+        if !CPU_FEATURES.get().unwrap().has_xsave() {
+            return FpuConfig {
+                instr: SaveInstr::Fxsave,
+                features: 0,
+                area_size: size_of::<FxSaveArea>(),
+                standard_size: size_of::<FxSaveArea>(),
+                mxcsr_mask,
+            };
+        }
+
+        let features = XCr0::read().bits() & XFEATURE_MASK_USER_RESTORE;
+        // SAFETY: The XSTATE CPUID leaf is supported if XSAVE is supported. Its subleaf 0
+        // reports the size of the standard area for the components enabled in `XCR0`.
+        let standard_size = (unsafe { __cpuid_count(XSTATE_CPUID, 0) }.ebx as usize)
+            .max(size_of::<FxSaveArea>() + size_of::<XSaveHeader>());
+        let (instr, area_size) = if has_cpu_feature(CpuFeature::Xsaves) {
+            // SAFETY: The XSTATE CPUID leaf is supported if XSAVE is supported. Its subleaf 1
+            // reports the size of the compacted area for the components enabled in
+            // `XCR0 | IA32_XSS`.
+            let res1 = unsafe { __cpuid_count(XSTATE_CPUID, 1) };
+            (SaveInstr::Xsaves, res1.ebx as usize)
+        } else {
+            let instr = if has_cpu_feature(CpuFeature::Xsaveopt) {
+                SaveInstr::Xsaveopt
+            } else {
+                SaveInstr::Xsave
+            };
+            (instr, standard_size)
+        };
+
+        FpuConfig {
+            instr,
+            features,
+            area_size: area_size.max(size_of::<FxSaveArea>() + size_of::<XSaveHeader>()),
+            standard_size,
+            mxcsr_mask,
+        }
+    });
+
+    if config.instr != SaveInstr::Xsaves {
+        return;
+    }
+    // No supervisor state components are managed, so `XSAVES` saves only the user ones.
+    // SAFETY: Reading `IA32_XSS` has no side effects.
+    let xss = unsafe { rdmsr(IA32_XSS) };
+    if xss != 0 {
+        // SAFETY: Clearing `IA32_XSS` only disables saving the supervisor state components.
+        unsafe { wrmsr(IA32_XSS, 0) };
+    }
+}
+
+/// Returns the bits of `MXCSR` that are supported by the CPU.
+fn query_mxcsr_mask() -> u32 {
+    /// The mask to use if `FXSAVE` reports zero, as specified by the Intel SDM.
+    const MXCSR_MASK_DEFAULT: u32 = 0xFFBF;
+
+    let mut area = XSaveUnit::new_area(size_of::<FxSaveArea>());
+    let area_ptr = area.as_mut_ptr().cast::<u8>();
+    // SAFETY: The area is large enough and properly aligned for `FXSAVE`, which only reads the
+    // FPU registers.
+    let mxcsr_mask = unsafe {
+        _fxsave64(area_ptr);
+        (*area_ptr.cast::<FxSaveArea>()).mxcsr_mask
+    };
+
+    if mxcsr_mask == 0 {
+        MXCSR_MASK_DEFAULT
+    } else {
+        mxcsr_mask
+    }
+}
+
+/// A 64-byte unit of the XSAVE area, which is required to be 64-byte aligned.
+#[repr(C, align(64))]
+#[derive(Clone, Copy)]
+struct XSaveUnit([u8; 64]);
+
+impl XSaveUnit {
+    const ZERO: Self = Self([0; 64]);
+
+    /// Allocates a zeroed area that is at least `size` bytes long.
+    fn new_area(size: usize) -> Box<[XSaveUnit]> {
+        vec![Self::ZERO; size.div_ceil(size_of::<XSaveUnit>())].into_boxed_slice()
+    }
+}
+
+// The legacy SSE/MMX FPU state format (as saved by `FXSAVE` and restored by the `FXRSTOR` instructions).
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Debug)]
+struct FxSaveArea {
+    control: u16,         // x87 FPU Control Word
+    status: u16,          // x87 FPU Status Word
+    tag: u16,             // x87 FPU Tag Word
+    op: u16,              // x87 FPU Last Instruction Opcode
+    ip: u32,              // x87 FPU Instruction Pointer Offset
+    cs: u32,              // x87 FPU Instruction Pointer Selector
+    dp: u32,              // x87 FPU Instruction Operand (Data) Pointer Offset
+    ds: u32,              // x87 FPU Instruction Operand (Data) Pointer Selector
+    mxcsr: u32,           // MXCSR Register State
+    mxcsr_mask: u32,      // MXCSR Mask
+    st_space: [u32; 32], // x87 FPU or MMX technology registers (ST0-ST7 or MM0-MM7, 128 bits per field)
+    xmm_space: [u32; 64], // XMM registers (XMM0-XMM15, 128 bits per field)
+    padding: [u32; 12],  // Padding
+    reserved: [u32; 12], // Software reserved
+}
+
+/// The header of the XSAVE area, which follows the legacy region.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct XSaveHeader {
+    /// The state components that are not in their initial configuration.
+    xstate_bv: u64,
+    /// The state components in the compacted format, if [`XCOMP_BV_COMPACTED_FORMAT`] is set.
+    xcomp_bv: u64,
+    reserved: [u64; 6],
+}
@@ -2,33 +2,24 @@
 
 //! CPU execution context control.
 
-use alloc::boxed::Box;
-use core::{
-    arch::x86_64::{_fxrstor64, _fxsave64, _xrstor64, _xsave64},
-    fmt::Debug,
-    sync::atomic::{AtomicBool, Ordering::Relaxed},
-};
-
 use bitflags::bitflags;
 use cfg_if::cfg_if;
-use log::debug;
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
-use spin::Once;
-use x86::bits64::segmentation::wrfsbase;
 use x86_64::registers::{
     control::{Cr0, Cr0Flags},
     rflags::RFlags,
-    xcontrol::XCr0,
 };
 
 use crate::{
-    arch::CPU_FEATURES,
+    arch::{cpu::fsgsbase, CPU_FEATURES},
     task::scheduler,
     trap::call_irq_callback_functions,
     user::{ReturnReason, UserContextApi, UserContextApiInternal},
 };
 
+mod fpu;
+
 cfg_if! {
     if #[cfg(feature = "cvm_guest")] {
         mod tdx;
@@ -39,6 +30,8 @@ cfg_if! {
 
 pub use x86::cpuid;
 
+pub(in crate::arch) use self::fpu::init as init_fpu;
+pub use self::fpu::{FpuState, KernelFpuGuard};
 pub use crate::arch::trap::{
     GeneralRegs as RawGeneralRegs, TrapFrame, UserContext as RawUserContext,
 };
@@ -108,7 +101,26 @@ impl UserContext {
     /// But if the user relies on the TLS pointer, make sure that the pointer is correctly set when
     /// entering the user space.
     pub fn activate_tls_pointer(&self) {
-        unsafe { wrfsbase(self.fsbase() as u64) }
+        fsgsbase::write_fsbase(self.fsbase());
+    }
+
+    /// Activates the GS base on the current CPU.
+    ///
+    /// The GS base takes effect when entering the user space.
+    pub fn activate_gsbase(&self) {
+        fsgsbase::write_user_gsbase(self.gsbase());
+    }
+
+    /// Saves the FS base and the GS base from the current CPU.
+    ///
+    /// The user space can change them with the FSGSBASE instructions without notifying the kernel.
+    fn save_fsgsbase(&mut self) {
+        if !fsgsbase::has_fsgsbase() {
+            return;
+        }
+
+        self.user_context.general.fsbase = fsgsbase::read_fsbase();
+        self.user_context.general.gsbase = fsgsbase::read_user_gsbase();
     }
 }
 
@@ -126,7 +138,12 @@ impl UserContextApiInternal for UserContext {
         // return when it is syscall or cpu exception type is Fault or Trap.
         let return_reason = loop {
             scheduler::might_preempt();
+            // The local IRQs are disabled until entering the user mode (see `run`), so the FPU
+            // state cannot be switched out after it is restored.
+            crate::arch::irq::disable_local();
+            fpu::restore_for_user_mode();
             self.user_context.run();
+            self.save_fsgsbase();
 
             match CpuException::to_cpu_exception(self.user_context.trap_num as u16) {
                 #[cfg(feature = "cvm_guest")]
@@ -420,195 +437,7 @@ cpu_context_impl_getter_setter!(
     [gsbase, set_gsbase]
 );
 
-/// The FPU state of user task.
-///
-/// This could be used for saving both legacy and modern state format.
-#[derive(Debug)]
-pub struct FpuState {
-    state_area: Box<XSaveArea>,
-    area_size: usize,
-    is_valid: AtomicBool,
-}
-
-// The legacy SSE/MMX FPU state format (as saved by `FXSAVE` and restored by the `FXRSTOR` instructions).
-#[repr(C, align(16))]
-#[derive(Clone, Copy, Debug)]
-struct FxSaveArea {
-    control: u16,         // x87 FPU Control Word
-    status: u16,          // x87 FPU Status Word
-    tag: u16,             // x87 FPU Tag Word
-    op: u16,              // x87 FPU Last Instruction Opcode
-    ip: u32,              // x87 FPU Instruction Pointer Offset
-    cs: u32,              // x87 FPU Instruction Pointer Selector
-    dp: u32,              // x87 FPU Instruction Operand (Data) Pointer Offset
-    ds: u32,              // x87 FPU Instruction Operand (Data) Pointer Selector
-    mxcsr: u32,           // MXCSR Register State
-    mxcsr_mask: u32,      // MXCSR Mask
-    st_space: [u32; 32], // x87 FPU or MMX technology registers (ST0-ST7 or MM0-MM7, 128 bits per field)
-    xmm_space: [u32; 64], // XMM registers (XMM0-XMM15, 128 bits per field)
-    padding: [u32; 12],  // Padding
-    reserved: [u32; 12], // Software reserved
-}
-
-/// The modern FPU state format (as saved by the `XSAVE`` and restored by the `XRSTOR` instructions).
-#[repr(C, align(64))]
-#[derive(Clone, Copy, Debug)]
-struct XSaveArea {
-    fxsave_area: FxSaveArea,
-    features: u64,
-    compaction: u64,
-    reserved: [u64; 6],
-    extended_state_area: [u8; MAX_XSAVE_AREA_SIZE - size_of::<FxSaveArea>() - 64],
-}
-
-impl XSaveArea {
-    fn init() -> Box<Self> {
-        let features = if CPU_FEATURES.get().unwrap().has_xsave() {
-            XCr0::read().bits() & XSTATE_MAX_FEATURES.get().unwrap()
-        } else {
-            0
-        };
-
-        let mut xsave_area = Box::<Self>::new_uninit();
-        let ptr = xsave_area.as_mut_ptr();
-        // SAFETY: it's safe to initialize the XSaveArea field then return the instance.
-        unsafe {
-            core::ptr::write_bytes(ptr, 0, 1);
-            (*ptr).fxsave_area.control = 0x37F;
-            (*ptr).fxsave_area.mxcsr = 0x1F80;
-            (*ptr).features = features;
-            xsave_area.assume_init()
-        }
-    }
-}
-
-impl FpuState {
-    /// Initializes a new instance.
-    pub fn init() -> Self {
-        let mut area_size = size_of::<FxSaveArea>();
-        if CPU_FEATURES.get().unwrap().has_xsave() {
-            area_size = area_size.max(*XSAVE_AREA_SIZE.get().unwrap());
-        }
-
-        Self {
-            state_area: XSaveArea::init(),
-            area_size,
-            is_valid: AtomicBool::new(true),
-        }
-    }
-
-    /// Returns whether the instance can contains valid state.
-    pub fn is_valid(&self) -> bool {
-        self.is_valid.load(Relaxed)
-    }
-
-    /// Save CPU's current FPU state into this instance.
-    pub fn save(&self) {
-        let mem_addr = &*self.state_area as *const _ as *mut u8;
-
-        if CPU_FEATURES.get().unwrap().has_xsave() {
-            unsafe { _xsave64(mem_addr, XFEATURE_MASK_USER_RESTORE) };
-        } else {
-            unsafe { _fxsave64(mem_addr) };
-        }
-
-        self.is_valid.store(true, Relaxed);
-
-        debug!("Save FPU state");
-    }
-
-    /// Restores CPU's FPU state from this instance.
-    pub fn restore(&self) {
-        if !self.is_valid() {
-            return;
-        }
-
-        let mem_addr = &*self.state_area as *const _ as *const u8;
-
-        if CPU_FEATURES.get().unwrap().has_xsave() {
-            let rs_mask = XFEATURE_MASK_USER_RESTORE & XSTATE_MAX_FEATURES.get().unwrap();
-
-            unsafe { _xrstor64(mem_addr, rs_mask) };
-        } else {
-            unsafe { _fxrstor64(mem_addr) };
-        }
-
-        self.is_valid.store(false, Relaxed);
-
-        debug!("Restore FPU state");
-    }
-
-    /// Clears the state of the instance.
-    ///
-    /// This method does not reset the underlying buffer that contains the
-    /// FPU state; it only marks the buffer __invalid__.
-    pub fn clear(&self) {
-        self.is_valid.store(false, Relaxed);
-    }
-}
-
-impl Clone for FpuState {
-    fn clone(&self) -> Self {
-        let mut state_area = XSaveArea::init();
-        state_area.fxsave_area = self.state_area.fxsave_area;
-        state_area.features = self.state_area.features;
-        state_area.compaction = self.state_area.compaction;
-        if self.area_size > size_of::<FxSaveArea>() {
-            let len = self.area_size - size_of::<FxSaveArea>() - 64;
-            state_area.extended_state_area[..len]
-                .copy_from_slice(&self.state_area.extended_state_area[..len]);
-        }
-
-        Self {
-            state_area,
-            area_size: self.area_size,
-            is_valid: AtomicBool::new(self.is_valid()),
-        }
-    }
-}
-
-impl Default for FpuState {
-    fn default() -> Self {
-        Self::init()
-    }
-}
-
-/// The XSTATE features (user & supervisor) supported by the processor.
-static XSTATE_MAX_FEATURES: Once<u64> = Once::new();
-
-/// Mask features which are restored when returning to user space.
-///
-/// X87 | SSE | AVX | OPMASK | ZMM_HI256 | HI16_ZMM
-const XFEATURE_MASK_USER_RESTORE: u64 = 0b1110_0111;
-
-/// The real size in bytes of the XSAVE area containing all states enabled by XCRO | IA32_XSS.
-static XSAVE_AREA_SIZE: Once<usize> = Once::new();
-
-/// The max size in bytes of the XSAVE area.
-const MAX_XSAVE_AREA_SIZE: usize = 4096;
-
 pub(in crate::arch) fn enable_essential_features() {
-    XSTATE_MAX_FEATURES.call_once(|| {
-        const XSTATE_CPUID: u32 = 0x0000000d;
-
-        // Find user xstates supported by the processor.
-        let res0 = cpuid::cpuid!(XSTATE_CPUID, 0);
-        let mut features = res0.eax as u64 + ((res0.edx as u64) << 32);
-
-        // Find supervisor xstates supported by the processor.
-        let res1 = cpuid::cpuid!(XSTATE_CPUID, 1);
-        features |= res1.ecx as u64 + ((res1.edx as u64) << 32);
-
-        features
-    });
-
-    XSAVE_AREA_SIZE.call_once(|| {
-        let cpuid = cpuid::CpuId::new();
-        let size = cpuid.get_extended_state_info().unwrap().xsave_size() as usize;
-        debug_assert!(size <= MAX_XSAVE_AREA_SIZE);
-        size
-    });
-
     if CPU_FEATURES.get().unwrap().has_fpu() {
         let mut cr0 = Cr0::read();
         cr0.remove(Cr0Flags::TASK_SWITCHED | Cr0Flags::EMULATE_COPROCESSOR);
@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The registry of the CPU features.
+//!
+//! The features are detected with CPUID once on the BSP, and are assumed to be the same on all
+//! the CPUs. The code that depends on a feature can either check it with [`has_cpu_feature`], or
+//! be patched at boot time with the alternative instructions (see [`super::alternative`]), which
+//! saves the runtime checks in the hot paths.
+
+use core::arch::x86_64::{__cpuid, __cpuid_count};
+
+use spin::Once;
+
+/// The CPU features that OSTD cares about.
+///
+/// The values are the indexes in the registry and are used by the alternative instructions, so
+/// they must be less than 64.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u16)]
+pub(crate) enum CpuFeature {
+    /// The enhanced `rep movsb` and `rep stosb` (ERMS).
+    Erms = 0,
+    /// The fast short `rep movsb` (FSRM).
+    Fsrm = 1,
+    /// The `clflushopt` instruction.
+    Clflushopt = 2,
+    /// The `rdfsbase`, `wrfsbase`, `rdgsbase`, and `wrgsbase` instructions.
+    Fsgsbase = 3,
+    /// The supervisor mode execution prevention (SMEP).
+    Smep = 4,
+    /// The supervisor mode access prevention (SMAP).
+    Smap = 5,
+    /// The user mode instruction prevention (UMIP).
+    Umip = 6,
+    /// The `xsaveopt` instruction.
+    Xsaveopt = 7,
+    /// The `xsaves` and `xrstors` instructions.
+    Xsaves = 8,
+    /// The AES instructions (AES-NI).
+    Aesni = 9,
+    /// The `rdrand` instruction.
+    Rdrand = 10,
+    /// The `rdseed` instruction.
+    Rdseed = 11,
+}
+
+/// The detected CPU features, where the bit at the index of a feature is set if it is supported.
+static CPU_FEATURES: Once<u64> = Once::new();
+
+/// Detects the CPU features.
+///
+/// This function should be called on the BSP before the APs are booted. The features may also be
+/// checked earlier (e.g., to randomize the stack canary), in which case they are detected then.
+pub(crate) fn init() {
+    CPU_FEATURES.call_once(detect_features);
+}
+
+fn detect_features() -> u64 {
+    let mut bits = 0;
+    let mut detect = |feature: CpuFeature, is_supported: bool| {
+        if is_supported {
+            bits |= 1 << feature as u16;
+        }
+    };
+
+    // SAFETY: CPUID is always available in the 64-bit mode.
+    let max_leaf = unsafe { __cpuid(0) }.eax;
+
+    // SAFETY: CPUID leaf 1 is always available in the 64-bit mode.
+    let leaf_1 = unsafe { __cpuid(1) };
+    detect(CpuFeature::Aesni, leaf_1.ecx & (1 << 25) != 0);
+    detect(CpuFeature::Rdrand, leaf_1.ecx & (1 << 30) != 0);
+
+    if max_leaf >= 7 {
+        // SAFETY: CPUID leaf 7 is supported, as checked above.
+        let leaf_7 = unsafe { __cpuid_count(7, 0) };
+        detect(CpuFeature::Erms, leaf_7.ebx & (1 << 9) != 0);
+        detect(CpuFeature::Fsrm, leaf_7.edx & (1 << 4) != 0);
+        detect(CpuFeature::Clflushopt, leaf_7.ebx & (1 << 23) != 0);
+        detect(CpuFeature::Fsgsbase, leaf_7.ebx & (1 << 0) != 0);
+        detect(CpuFeature::Smep, leaf_7.ebx & (1 << 7) != 0);
+        detect(CpuFeature::Smap, leaf_7.ebx & (1 << 20) != 0);
+        detect(CpuFeature::Umip, leaf_7.ecx & (1 << 2) != 0);
+        detect(CpuFeature::Rdseed, leaf_7.ebx & (1 << 18) != 0);
+    }
+
+    if max_leaf >= 0xd {
+        // SAFETY: CPUID leaf 0xd is supported, as checked above.
+        let leaf_d_1 = unsafe { __cpuid_count(0xd, 1) };
+        detect(CpuFeature::Xsaveopt, leaf_d_1.eax & (1 << 0) != 0);
+        detect(CpuFeature::Xsaves, leaf_d_1.eax & (1 << 3) != 0);
+    }
+
+    bits
+}
+
+/// Returns whether the CPU supports the feature.
+pub(crate) fn has_cpu_feature(feature: CpuFeature) -> bool {
+    has_cpu_feature_index(feature as u16)
+}
+
+/// Returns whether the CPU supports the feature at the index.
+pub(super) fn has_cpu_feature_index(index: u16) -> bool {
+    let features = *CPU_FEATURES.call_once(detect_features);
+    index < u64::BITS as u16 && features & (1 << index) != 0
+}
@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Access to the FS and GS base registers.
+//!
+//! The `rdfsbase` and `wrfsbase` instructions are used if the CPU supports them, since they are
+//! much cheaper than accessing the corresponding MSRs.
+//!
+//! The kernel uses the GS base to access CPU-local data. While the CPU runs in the kernel mode,
+//! the GS base of the user space is kept in the `IA32_KERNEL_GSBASE` MSR (see `swapgs` in the
+//! syscall and trap entries). So the user GS base is always accessed via the MSR.
+
+use x86::msr::{rdmsr, wrmsr, IA32_KERNEL_GSBASE};
+
+use super::{
+    alternative::alternative,
+    feature::{has_cpu_feature, CpuFeature},
+};
+
+/// Returns whether the CPU supports the FSGSBASE instructions.
+pub(crate) fn has_fsgsbase() -> bool {
+    has_cpu_feature(CpuFeature::Fsgsbase)
+}
+
+/// Reads the FS base of the current CPU.
+pub(crate) fn read_fsbase() -> usize {
+    let fsbase: usize;
+    // SAFETY: Reading the FS base has no side effects. The `rdmsr` of `IA32_FS_BASE` is patched
+    // with `rdfsbase` only if the FSGSBASE instructions are supported (and thus enabled).
+    unsafe {
+        alternative!(
+            CpuFeature::Fsgsbase,
+            "mov ecx, 0xc0000100\nrdmsr\nshl rdx, 32\nor rax, rdx",
+            "rdfsbase rax",
+            out("rax") fsbase,
+            out("rcx") _,
+            out("rdx") _,
+            options(nomem, nostack, preserves_flags)
+        )
+    };
+    fsbase
+}
+
+/// Writes the FS base of the current CPU.
+///
+/// The kernel does not use the FS base, so this only affects the user space.
+pub(crate) fn write_fsbase(fsbase: usize) {
+    // SAFETY: The value of the FS base won't affect kernel code. The `wrmsr` of `IA32_FS_BASE`
+    // is patched with `wrfsbase` only if the FSGSBASE instructions are supported (and thus
+    // enabled).
+    unsafe {
+        alternative!(
+            CpuFeature::Fsgsbase,
+            "mov ecx, 0xc0000100\nmov eax, edi\nmov rdx, rdi\nshr rdx, 32\nwrmsr",
+            "wrfsbase rdi",
+            in("rdi") fsbase,
+            out("rax") _,
+            out("rcx") _,
+            out("rdx") _,
+            options(nostack)
+        )
+    };
+}
+
+/// Reads the user GS base of the current CPU.
+///
+/// This method must be called in the kernel mode, i.e., after `swapgs` swaps in the kernel GS
+/// base.
+pub(crate) fn read_user_gsbase() -> usize {
+    // SAFETY: Reading the user GS base has no side effects.
+    unsafe { rdmsr(IA32_KERNEL_GSBASE) as usize }
+}
+
+/// Writes the user GS base of the current CPU.
+///
+/// This method must be called in the kernel mode, i.e., after `swapgs` swaps in the kernel GS
+/// base.
+pub(crate) fn write_user_gsbase(gsbase: usize) {
+    // SAFETY: The user GS base will only be swapped in when returning to the user space, so its
+    // value won't affect kernel code.
+    unsafe { wrmsr(IA32_KERNEL_GSBASE, gsbase as u64) }
+}
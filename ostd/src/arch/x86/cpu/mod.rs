@@ -2,8 +2,13 @@
 
 //! CPU context & state control and CPU local memory.
 
+pub mod aesni;
+pub(crate) mod alternative;
 pub mod context;
+pub(crate) mod feature;
+pub(crate) mod fsgsbase;
 pub mod local;
+pub(crate) mod smap;
 
 /// Halts the CPU.
 ///
@@ -17,3 +22,52 @@ pub fn sleep_for_interrupt() {
     crate::task::atomic_mode::might_sleep();
     x86_64::instructions::hlt();
 }
+
+/// Returns whether the CPU supports the `MONITOR` and `MWAIT` instructions.
+pub fn has_mwait() -> bool {
+    crate::arch::CPU_FEATURES.get().unwrap().has_monitor_mwait()
+}
+
+/// Halts the CPU with the `MWAIT` instruction.
+///
+/// This function halts the CPU until the next interrupt is received, like
+/// [`sleep_for_interrupt`]. The `hint` is passed to `MWAIT` in `EAX` to
+/// select the target C-state, e.g., zero for the C-1 state. Entering a deeper
+/// C-state saves more power at the cost of a longer wakeup latency.
+///
+/// Since the function sleeps the CPU, it should not be used within an atomic
+/// mode ([`crate::task::atomic_mode`]).
+///
+/// # Panics
+///
+/// This function panics if the CPU does not support `MWAIT` (see
+/// [`has_mwait`]).
+#[track_caller]
+pub fn sleep_for_interrupt_with_mwait(hint: u32) {
+    /// The monitored address, which is never written.
+    ///
+    /// So the CPU is only woken up by interrupts.
+    static MONITOR_LINE: u64 = 0;
+
+    crate::task::atomic_mode::might_sleep();
+    assert!(has_mwait());
+
+    // SAFETY: `MONITOR` only arms the address monitoring hardware with a valid
+    // address, and `MWAIT` only waits until an interrupt or a write to the
+    // monitored address occurs. Neither of them accesses the memory.
+    unsafe {
+        core::arch::asm!(
+            "monitor",
+            in("rax") &MONITOR_LINE as *const u64,
+            in("ecx") 0,
+            in("edx") 0,
+            options(nostack, preserves_flags),
+        );
+        core::arch::asm!(
+            "mwait",
+            in("eax") hint,
+            in("ecx") 0,
+            options(nostack, preserves_flags),
+        );
+    }
+}
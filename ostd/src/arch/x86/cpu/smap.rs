@@ -0,0 +1,68 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The supervisor mode access prevention (SMAP).
+//!
+//! If SMAP is enabled, the kernel cannot access the user pages unless the `AC` flag in `RFLAGS`
+//! is set. The kernel sets the flag only in the user-access windows (see [`UserAccessGuard`]),
+//! i.e., while it copies data from or to the user space in `__memcpy_fallible` and
+//! `__memset_fallible`. Any other access to the user pages causes a page fault.
+//!
+//! The `AC` flag must be cleared whenever the CPU enters the kernel, since the CPU may be
+//! interrupted in a user-access window, or the user space may set the flag by itself. The
+//! `syscall` instruction masks the flag (see `RFLAGS_MASK` in the syscall module), while the
+//! other entries clear it with [`close_user_access`].
+
+use super::{
+    alternative::alternative,
+    feature::{has_cpu_feature, CpuFeature},
+};
+
+/// Returns whether the CPU supports SMAP.
+pub(crate) fn has_smap() -> bool {
+    has_cpu_feature(CpuFeature::Smap)
+}
+
+/// Clears the `AC` flag, so that the kernel cannot access the user pages.
+pub(crate) fn close_user_access() {
+    // SAFETY: Clearing the `AC` flag only forbids the accesses to the user pages. The 3-byte
+    // `nop` is patched with `clac` only if SMAP is supported.
+    unsafe {
+        alternative!(
+            CpuFeature::Smap,
+            ".byte 0x0f, 0x1f, 0x00",
+            "clac",
+            options(nostack)
+        )
+    };
+}
+
+/// A guard of a user-access window, in which the kernel can access the user pages.
+///
+/// The window is closed when the guard is dropped.
+pub(crate) struct UserAccessGuard {
+    _private: (),
+}
+
+impl UserAccessGuard {
+    /// Opens a user-access window.
+    pub(crate) fn new() -> Self {
+        // SAFETY: Setting the `AC` flag only allows the accesses to the user pages. The accesses
+        // are still checked against the page table. The 3-byte `nop` is patched with `stac` only
+        // if SMAP is supported.
+        unsafe {
+            alternative!(
+                CpuFeature::Smap,
+                ".byte 0x0f, 0x1f, 0x00",
+                "stac",
+                options(nostack)
+            )
+        };
+        Self { _private: () }
+    }
+}
+
+impl Drop for UserAccessGuard {
+    fn drop(&mut self) {
+        close_user_access();
+    }
+}
@@ -0,0 +1,577 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A stub of the GDB remote serial protocol, which debugs the kernel on bare metal.
+//!
+//! The gdbstub of QEMU is not available on real hardware. Instead, this stub talks to GDB over
+//! a serial port dedicated to it, which is selected by the `ostd.gdbstub=<port>` kernel command
+//! line option, where the port is `ttyS1`, `ttyS2`, or `ttyS3` (`ttyS0` is the console). The
+//! port runs at 38400 baud, so GDB connects to it on the other machine by
+//!
+//! ```text
+//! (gdb) set serial baud 38400
+//! (gdb) target remote /dev/ttyUSB0
+//! ```
+//!
+//! With the `ostd.gdbwait` option, the kernel stops at a breakpoint as soon as the stub is
+//! initialized, and waits for GDB to connect. Otherwise, GDB breaks into the running kernel by
+//! sending Ctrl-C or any packet, since the port is polled in the timer interrupts of the BSP.
+//!
+//! The stub supports reading and writing the registers and the kernel memory, the software
+//! breakpoints (i.e., `int3`), and single-stepping (i.e., the trap flag). It has the following
+//! limitations:
+//!  - Only the CPU that stops is debugged. The other CPUs keep running, and they wait for the
+//!    stub if they stop at the same time.
+//!  - The stack pointer and the segment registers cannot be changed.
+//!  - The hardware breakpoints and watchpoints are not supported.
+//!  - It is not supported on other architectures.
+
+mod packet;
+
+use alloc::vec::Vec;
+
+use log::{info, warn};
+use spin::Once;
+use x86_64::registers::{
+    control::{Cr0, Cr0Flags},
+    rflags::RFlags,
+    segmentation::{Segment, DS, ES, FS, GS, SS},
+};
+
+use self::packet::{parse_hex, parse_hex_bytes, push_hex_bytes, Connection, MAX_PACKET_SIZE};
+use crate::{
+    arch::{device::serial::SerialPort, trap::TrapFrame},
+    boot::EARLY_INFO,
+    cpu::{context::CpuException, CpuId},
+    io::reserve_io_port_range,
+    mm::{kspace::KERNEL_PAGE_TABLE, PageFlags, Vaddr},
+    sync::SpinLock,
+    trap::irq::disable_local,
+};
+
+/// The serial ports that can be dedicated to the stub.
+const PORTS: [(&str, u16); 3] = [("ttyS1", 0x2F8), ("ttyS2", 0x3E8), ("ttyS3", 0x2E8)];
+
+reserve_io_port_range!(0x2F8..0x300);
+reserve_io_port_range!(0x3E8..0x3F0);
+reserve_io_port_range!(0x2E8..0x2F0);
+
+/// The maximum number of the software breakpoints.
+const MAX_BREAKPOINTS: usize = 64;
+
+/// The `int3` instruction.
+const INT3: u8 = 0xCC;
+
+/// The signals in the stop replies.
+const SIGINT: u8 = 2;
+const SIGTRAP: u8 = 5;
+
+static STUB: Once<SpinLock<GdbStub>> = Once::new();
+
+/// Initializes the stub if it is enabled in the kernel command line.
+///
+/// This function should be called on the BSP after the kernel page table is activated.
+pub(crate) fn init() {
+    let kcmdline = EARLY_INFO.get().unwrap().kernel_cmdline;
+
+    let mut port = None;
+    let mut is_waiting = false;
+    for arg in kcmdline.split(' ') {
+        if let Some(name) = arg.strip_prefix("ostd.gdbstub=") {
+            port = PORTS.iter().find(|(port_name, _)| *port_name == name);
+            if port.is_none() {
+                warn!("The serial port `{}` cannot be used by the GDB stub", name);
+            }
+        } else if matches!(arg, "ostd.gdbwait" | "ostd.gdbwait=1" | "ostd.gdbwait=on") {
+            is_waiting = true;
+        }
+    }
+    let Some(&(name, port)) = port else {
+        return;
+    };
+
+    // SAFETY: The port is a standard serial port, which is reserved above and only used here.
+    let port = unsafe { SerialPort::new(port) };
+    STUB.call_once(|| SpinLock::new(GdbStub::new(Connection::new(port))));
+    info!("The GDB stub is listening on {}", name);
+
+    if is_waiting {
+        info!("Waiting for GDB to connect");
+        breakpoint();
+    }
+}
+
+/// Polls the serial port for the requests from GDB to break into the running kernel.
+///
+/// This function is called in the timer interrupts of the BSP.
+pub(super) fn poll() {
+    let Some(stub) = STUB.get() else {
+        return;
+    };
+    // Another CPU is being debugged.
+    let Some(mut stub) = stub.try_lock() else {
+        return;
+    };
+
+    let mut is_requested = false;
+    while let Some(byte) = stub.conn.try_recv_byte() {
+        // Besides Ctrl-C, a packet is also taken as a request (e.g., when GDB connects). The
+        // packet is retransmitted by GDB after the kernel stops, since it is not acknowledged.
+        if byte == 0x03 || byte == b'$' {
+            is_requested = true;
+        }
+    }
+    if !is_requested {
+        return;
+    }
+
+    stub.is_interrupting = true;
+    drop(stub);
+    breakpoint();
+}
+
+/// Stops the current CPU and reports it to GDB.
+fn breakpoint() {
+    // SAFETY: The breakpoint exception is handled by the stub, which is initialized.
+    unsafe { core::arch::asm!("int3") };
+}
+
+/// Handles a breakpoint or debug exception in the kernel mode.
+///
+/// Returns false if the exception is not caused by the stub, which should be taken as fatal.
+pub(super) fn handle_exception(exception: CpuException, f: &mut TrapFrame) -> bool {
+    let Some(stub) = STUB.get() else {
+        return false;
+    };
+    // The local IRQs are disabled on the exceptions, and they stay disabled in the stub.
+    let irq_guard = disable_local();
+    let cpu = irq_guard.current_cpu();
+    let mut stub = stub.lock();
+
+    let signal = match exception {
+        CpuException::BREAKPOINT => {
+            if let Some((stepping_cpu, _)) = stub.step
+                && stepping_cpu == cpu
+            {
+                stub.step = None;
+                f.rflags &= !(RFlags::TRAP_FLAG.bits() as usize);
+            }
+
+            let addr = f.rip - 1;
+            if stub.breakpoint_at(addr).is_some() {
+                f.rip = addr;
+                SIGTRAP
+            } else if read_byte(addr) == Some(INT3) {
+                // An `int3` in the code, e.g., the one in `breakpoint`.
+                if core::mem::take(&mut stub.is_interrupting) {
+                    SIGINT
+                } else {
+                    SIGTRAP
+                }
+            } else {
+                // The breakpoint was removed after it was hit, so the instruction is restored
+                // and executed again.
+                f.rip = addr;
+                return true;
+            }
+        }
+        CpuException::DEBUG => match stub.step {
+            Some((stepping_cpu, step)) if stepping_cpu == cpu => {
+                stub.step = None;
+                f.rflags &= !(RFlags::TRAP_FLAG.bits() as usize);
+                match step {
+                    Step::Into => SIGTRAP,
+                    Step::Over => {
+                        stub.insert_breakpoints(None);
+                        return true;
+                    }
+                }
+            }
+            _ => return false,
+        },
+        _ => return false,
+    };
+
+    stub.handle_stop(f, signal, cpu);
+    true
+}
+
+/// The state of the stub.
+struct GdbStub {
+    conn: Connection,
+    /// The buffer of the received packets.
+    ///
+    /// The buffers are allocated in advance and never grow, since the stub may be entered when
+    /// the heap is locked.
+    rx_buf: Vec<u8>,
+    /// The buffer of the packets to send.
+    tx_buf: Vec<u8>,
+    breakpoints: Vec<Breakpoint>,
+    /// The CPU that is single-stepping, and why.
+    step: Option<(CpuId, Step)>,
+    /// Whether the kernel is being stopped by GDB.
+    is_interrupting: bool,
+}
+
+/// A software breakpoint.
+struct Breakpoint {
+    addr: Vaddr,
+    /// The original byte at the address if the breakpoint is inserted.
+    saved_byte: Option<u8>,
+}
+
+#[derive(Clone, Copy)]
+enum Step {
+    /// Single-stepping requested by GDB, after which the CPU stops.
+    Into,
+    /// Single-stepping over a breakpoint to continue, after which the breakpoint is inserted.
+    Over,
+}
+
+impl GdbStub {
+    fn new(conn: Connection) -> Self {
+        Self {
+            conn,
+            rx_buf: Vec::with_capacity(MAX_PACKET_SIZE),
+            tx_buf: Vec::with_capacity(MAX_PACKET_SIZE),
+            breakpoints: Vec::with_capacity(MAX_BREAKPOINTS),
+            step: None,
+            is_interrupting: false,
+        }
+    }
+
+    /// Reports the stop to GDB and serves its requests until the CPU resumes.
+    fn handle_stop(&mut self, f: &mut TrapFrame, signal: u8, cpu: CpuId) {
+        // The breakpoints are not inserted while GDB inspects the memory.
+        self.remove_breakpoints();
+        self.reply_stop(signal);
+
+        let mut packet = core::mem::take(&mut self.rx_buf);
+        loop {
+            self.conn.recv_packet(&mut packet);
+            self.tx_buf.clear();
+
+            let Some((&command, args)) = packet.split_first() else {
+                self.send_reply();
+                continue;
+            };
+            match command {
+                b'?' => self.reply_stop(signal),
+                b'g' => self.read_registers(f),
+                b'G' => self.write_registers(f, args),
+                b'm' => self.read_memory(args),
+                b'M' => self.write_memory(args),
+                b'Z' => self.set_breakpoint(args, true),
+                b'z' => self.set_breakpoint(args, false),
+                b'c' | b's' => {
+                    if let Some(addr) = parse_hex(args) {
+                        f.rip = addr as usize;
+                    }
+                    self.resume(f, command == b's', cpu);
+                    break;
+                }
+                // The kernel cannot be killed, so `k` detaches GDB like `D`.
+                b'D' | b'k' => {
+                    self.breakpoints.clear();
+                    if command == b'D' {
+                        self.reply(b"OK");
+                    }
+                    self.resume(f, false, cpu);
+                    break;
+                }
+                b'H' => self.reply(b"OK"),
+                b'q' if args.starts_with(b"Supported") => {
+                    self.tx_buf.extend_from_slice(b"PacketSize=");
+                    push_hex_bytes(&mut self.tx_buf, &(MAX_PACKET_SIZE as u16).to_be_bytes());
+                    self.send_reply();
+                }
+                b'q' if args == b"Attached" => self.reply(b"1"),
+                // An empty reply means that the packet is not supported.
+                _ => self.send_reply(),
+            }
+        }
+        self.rx_buf = packet;
+    }
+
+    fn resume(&mut self, f: &mut TrapFrame, is_stepping: bool, cpu: CpuId) {
+        let ip = f.rip;
+        let step = if is_stepping {
+            Some(Step::Into)
+        } else if self.breakpoint_at(ip).is_some() {
+            // The breakpoint at the current instruction is inserted after it is executed.
+            Some(Step::Over)
+        } else {
+            None
+        };
+
+        if let Some(step) = step {
+            self.step = Some((cpu, step));
+            f.rflags |= RFlags::TRAP_FLAG.bits() as usize;
+            self.insert_breakpoints(Some(ip));
+        } else {
+            f.rflags &= !(RFlags::TRAP_FLAG.bits() as usize);
+            self.insert_breakpoints(None);
+        }
+    }
+
+    fn breakpoint_at(&self, addr: Vaddr) -> Option<usize> {
+        self.breakpoints.iter().position(|bp| bp.addr == addr)
+    }
+
+    /// Inserts the breakpoints, except the one at the address.
+    fn insert_breakpoints(&mut self, except: Option<Vaddr>) {
+        for bp in self.breakpoints.iter_mut() {
+            if bp.saved_byte.is_some() || Some(bp.addr) == except {
+                continue;
+            }
+            let Some(byte) = read_byte(bp.addr) else {
+                continue;
+            };
+            if write_byte(bp.addr, INT3) {
+                bp.saved_byte = Some(byte);
+            }
+        }
+    }
+
+    fn remove_breakpoints(&mut self) {
+        for bp in self.breakpoints.iter_mut() {
+            if let Some(byte) = bp.saved_byte.take() {
+                write_byte(bp.addr, byte);
+            }
+        }
+    }
+
+    fn reply(&mut self, data: &[u8]) {
+        self.tx_buf.clear();
+        self.tx_buf.extend_from_slice(data);
+        self.send_reply();
+    }
+
+    fn send_reply(&self) {
+        self.conn.send_packet(&self.tx_buf);
+    }
+
+    fn reply_stop(&mut self, signal: u8) {
+        self.tx_buf.clear();
+        self.tx_buf.push(b'S');
+        push_hex_bytes(&mut self.tx_buf, &[signal]);
+        self.send_reply();
+    }
+
+    /// Replies the registers in the layout of GDB for x86-64, i.e., the general-purpose
+    /// registers and `rip` in 64 bits, followed by `eflags` and the segment registers in 32 bits.
+    ///
+    /// The other registers (e.g., the FPU registers) are reported as unavailable.
+    fn read_registers(&mut self, f: &TrapFrame) {
+        for reg in [
+            f.rax, f.rbx, f.rcx, f.rdx, f.rsi, f.rdi, f.rbp, f.rsp, f.r8, f.r9, f.r10, f.r11,
+            f.r12, f.r13, f.r14, f.r15, f.rip,
+        ] {
+            push_hex_bytes(&mut self.tx_buf, &(reg as u64).to_le_bytes());
+        }
+
+        for reg in [
+            f.rflags as u32,
+            f.cs as u32,
+            SS::get_reg().0 as u32,
+            DS::get_reg().0 as u32,
+            ES::get_reg().0 as u32,
+            FS::get_reg().0 as u32,
+            GS::get_reg().0 as u32,
+        ] {
+            push_hex_bytes(&mut self.tx_buf, &reg.to_le_bytes());
+        }
+
+        self.send_reply();
+    }
+
+    /// Writes the registers in the same layout as [`Self::read_registers`].
+    fn write_registers(&mut self, f: &mut TrapFrame, args: &[u8]) {
+        let mut bytes = parse_hex_bytes(args);
+        let mut next_reg = |size: usize| {
+            let mut reg = [0u8; 8];
+            for byte in reg.iter_mut().take(size) {
+                *byte = bytes.next()??;
+            }
+            Some(u64::from_le_bytes(reg) as usize)
+        };
+
+        // The values are parsed before any register is written, in case the packet is invalid.
+        let mut values = [0usize; 18];
+        for (i, value) in values.iter_mut().enumerate() {
+            let size = if i < 17 { 8 } else { 4 };
+            let Some(reg) = next_reg(size) else {
+                self.reply(b"E22");
+                return;
+            };
+            *value = reg;
+        }
+
+        // The stack pointer is not restored from the trap frame, so it cannot be changed.
+        let mut rsp = f.rsp;
+        let regs = [
+            &mut f.rax,
+            &mut f.rbx,
+            &mut f.rcx,
+            &mut f.rdx,
+            &mut f.rsi,
+            &mut f.rdi,
+            &mut f.rbp,
+            &mut rsp,
+            &mut f.r8,
+            &mut f.r9,
+            &mut f.r10,
+            &mut f.r11,
+            &mut f.r12,
+            &mut f.r13,
+            &mut f.r14,
+            &mut f.r15,
+            &mut f.rip,
+            &mut f.rflags,
+        ];
+        for (reg, value) in regs.into_iter().zip(values) {
+            *reg = value;
+        }
+
+        self.reply(b"OK");
+    }
+
+    /// Replies the memory of `m<addr>,<len>`.
+    ///
+    /// The reply is shorter if a part of the memory is not mapped.
+    fn read_memory(&mut self, args: &[u8]) {
+        let Some((addr, len)) = parse_addr_len(args) else {
+            self.reply(b"E22");
+            return;
+        };
+
+        let len = len.min(self.tx_buf.capacity() / 2);
+        for addr in addr..addr.saturating_add(len) {
+            let Some(byte) = read_byte(addr) else {
+                break;
+            };
+            push_hex_bytes(&mut self.tx_buf, &[byte]);
+        }
+
+        if self.tx_buf.is_empty() && len > 0 {
+            self.reply(b"E14");
+        } else {
+            self.send_reply();
+        }
+    }
+
+    /// Writes the memory of `M<addr>,<len>:<bytes>`.
+    ///
+    /// The read-only memory (e.g., the kernel code) can also be written.
+    fn write_memory(&mut self, args: &[u8]) {
+        let Some(colon) = args.iter().position(|&byte| byte == b':') else {
+            self.reply(b"E22");
+            return;
+        };
+        let (Some((addr, len)), bytes) = (parse_addr_len(&args[..colon]), &args[colon + 1..])
+        else {
+            self.reply(b"E22");
+            return;
+        };
+        if bytes.len() != len * 2 {
+            self.reply(b"E22");
+            return;
+        }
+
+        for (addr, byte) in (addr..).zip(parse_hex_bytes(bytes)) {
+            let Some(byte) = byte else {
+                self.reply(b"E22");
+                return;
+            };
+            if !write_byte(addr, byte) {
+                self.reply(b"E14");
+                return;
+            }
+        }
+
+        self.reply(b"OK");
+    }
+
+    /// Sets (i.e., `Z0,<addr>,<kind>`) or clears (i.e., `z0,<addr>,<kind>`) a software breakpoint.
+    ///
+    /// The other types of breakpoints are not supported.
+    fn set_breakpoint(&mut self, args: &[u8], is_set: bool) {
+        let Some(args) = args.strip_prefix(b"0,") else {
+            self.send_reply();
+            return;
+        };
+        let Some((addr, _kind)) = parse_addr_len(args) else {
+            self.reply(b"E22");
+            return;
+        };
+
+        if let Some(index) = self.breakpoint_at(addr) {
+            if !is_set {
+                // The breakpoints are not inserted while the CPU is stopped.
+                self.breakpoints.swap_remove(index);
+            }
+        } else if is_set {
+            if read_byte(addr).is_none() {
+                self.reply(b"E14");
+                return;
+            }
+            if self.breakpoints.len() == self.breakpoints.capacity() {
+                self.reply(b"E28");
+                return;
+            }
+            self.breakpoints.push(Breakpoint {
+                addr,
+                saved_byte: None,
+            });
+        }
+
+        self.reply(b"OK");
+    }
+}
+
+/// Parses `<addr>,<len>` in hexadecimal digits.
+fn parse_addr_len(args: &[u8]) -> Option<(Vaddr, usize)> {
+    let comma = args.iter().position(|&byte| byte == b',')?;
+    let addr = parse_hex(&args[..comma])?;
+    let len = parse_hex(&args[comma + 1..])?;
+    Some((addr as Vaddr, len as usize))
+}
+
+/// Returns the flags of the page that maps the address in the kernel page table.
+///
+/// The user memory is not accessed, since it is not mapped in the kernel page table.
+fn page_flags(addr: Vaddr) -> Option<PageFlags> {
+    let (_, prop) = KERNEL_PAGE_TABLE.get()?.query(addr)?;
+    Some(prop.flags)
+}
+
+fn read_byte(addr: Vaddr) -> Option<u8> {
+    if !page_flags(addr)?.contains(PageFlags::R) {
+        return None;
+    }
+
+    // SAFETY: The address is mapped and readable. GDB is trusted to read the kernel memory.
+    Some(unsafe { core::ptr::read_volatile(addr as *const u8) })
+}
+
+fn write_byte(addr: Vaddr, byte: u8) -> bool {
+    let Some(flags) = page_flags(addr) else {
+        return false;
+    };
+
+    let cr0 = Cr0::read();
+    // The write protection is disabled temporarily to write the read-only memory, which is safe
+    // since the local IRQs are disabled in the stub.
+    if !flags.contains(PageFlags::W) {
+        // SAFETY: Disabling the write protection does not break the memory safety by itself.
+        unsafe { Cr0::write(cr0 - Cr0Flags::WRITE_PROTECT) };
+    }
+
+    // SAFETY: The address is mapped. GDB is trusted to write the kernel memory, e.g., to insert
+    // the breakpoints in the kernel code.
+    unsafe { core::ptr::write_volatile(addr as *mut u8, byte) };
+
+    // SAFETY: The original value of CR0 is restored.
+    unsafe { Cr0::write(cr0) };
+
+    true
+}
@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The packets of the GDB remote serial protocol.
+//!
+//! A packet is sent as `$<data>#<checksum>`, where the checksum is the sum of the data bytes
+//! modulo 256 in two hexadecimal digits. The receiver acknowledges it with `+`, or requests a
+//! retransmission with `-`. The binary data are encoded in hexadecimal digits.
+//!
+//! Ref: <https://sourceware.org/gdb/current/onlinedocs/gdb.html/Overview.html>
+
+use alloc::vec::Vec;
+use core::hint::spin_loop;
+
+use crate::arch::device::serial::SerialPort;
+
+/// The maximum number of the bytes in a packet, which is told to GDB in `qSupported`.
+pub(super) const MAX_PACKET_SIZE: usize = 4096;
+
+bitflags::bitflags! {
+    struct LineSts: u8 {
+        const INPUT_FULL = 1;
+        const OUTPUT_EMPTY = 1 << 5;
+    }
+}
+
+/// A connection to GDB over a serial port.
+pub(super) struct Connection {
+    port: SerialPort,
+}
+
+impl Connection {
+    pub(super) fn new(port: SerialPort) -> Self {
+        port.init();
+        Self { port }
+    }
+
+    fn line_sts(&self) -> LineSts {
+        LineSts::from_bits_truncate(self.port.line_status())
+    }
+
+    fn send_byte(&self, byte: u8) {
+        while !self.line_sts().contains(LineSts::OUTPUT_EMPTY) {
+            spin_loop();
+        }
+        self.port.send(byte);
+    }
+
+    fn recv_byte(&self) -> u8 {
+        loop {
+            if let Some(byte) = self.try_recv_byte() {
+                return byte;
+            }
+            spin_loop();
+        }
+    }
+
+    /// Receives a byte if there is one, without waiting.
+    pub(super) fn try_recv_byte(&self) -> Option<u8> {
+        if self.line_sts().contains(LineSts::INPUT_FULL) {
+            Some(self.port.recv())
+        } else {
+            None
+        }
+    }
+
+    /// Receives a packet into the buffer and acknowledges it.
+    ///
+    /// The packets that are corrupted or too long for the buffer are discarded, and GDB is
+    /// requested to retransmit them. The buffer never grows beyond its capacity.
+    pub(super) fn recv_packet(&self, buf: &mut Vec<u8>) {
+        'packet: loop {
+            // The bytes before a packet (e.g., the acknowledgements) are ignored.
+            while self.recv_byte() != b'$' {}
+
+            buf.clear();
+            let mut checksum = 0u8;
+            loop {
+                match self.recv_byte() {
+                    b'#' => break,
+                    // A new packet starts before the current one ends.
+                    b'$' => {
+                        buf.clear();
+                        checksum = 0;
+                    }
+                    byte if buf.len() < buf.capacity() => {
+                        buf.push(byte);
+                        checksum = checksum.wrapping_add(byte);
+                    }
+                    _ => {
+                        self.send_byte(b'-');
+                        continue 'packet;
+                    }
+                }
+            }
+
+            let high = hex_digit(self.recv_byte());
+            let low = hex_digit(self.recv_byte());
+            if let (Some(high), Some(low)) = (high, low)
+                && (high << 4 | low) == checksum
+            {
+                self.send_byte(b'+');
+                return;
+            }
+            self.send_byte(b'-');
+        }
+    }
+
+    /// Sends a packet until it is acknowledged.
+    pub(super) fn send_packet(&self, data: &[u8]) {
+        loop {
+            self.send_byte(b'$');
+            let mut checksum = 0u8;
+            for &byte in data {
+                self.send_byte(byte);
+                checksum = checksum.wrapping_add(byte);
+            }
+            self.send_byte(b'#');
+            self.send_byte(HEX_DIGITS[(checksum >> 4) as usize]);
+            self.send_byte(HEX_DIGITS[(checksum & 0xF) as usize]);
+
+            loop {
+                match self.recv_byte() {
+                    b'+' => return,
+                    b'-' => break,
+                    _ => continue,
+                }
+            }
+        }
+    }
+}
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+fn hex_digit(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|digit| digit as u8)
+}
+
+/// Parses a number in hexadecimal digits.
+pub(super) fn parse_hex(digits: &[u8]) -> Option<u64> {
+    if digits.is_empty() || digits.len() > 16 {
+        return None;
+    }
+    digits
+        .iter()
+        .try_fold(0u64, |num, &byte| Some(num << 4 | hex_digit(byte)? as u64))
+}
+
+/// Parses the bytes in pairs of hexadecimal digits.
+pub(super) fn parse_hex_bytes(digits: &[u8]) -> impl Iterator<Item = Option<u8>> + '_ {
+    digits
+        .chunks(2)
+        .map(|pair| Some(hex_digit(pair[0])? << 4 | hex_digit(*pair.get(1)?)?))
+}
+
+/// Appends the bytes in pairs of hexadecimal digits.
+///
+/// The bytes that do not fit in the capacity of the buffer are dropped.
+pub(super) fn push_hex_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    for &byte in bytes {
+        if buf.capacity() - buf.len() < 2 {
+            return;
+        }
+        buf.push(HEX_DIGITS[(byte >> 4) as usize]);
+        buf.push(HEX_DIGITS[(byte & 0xF) as usize]);
+    }
+}
@@ -0,0 +1,184 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The kernel page-table isolation (KPTI).
+//!
+//! KPTI mitigates the side-channel attacks that read the kernel memory from the user mode (e.g.,
+//! Meltdown) by unmapping the kernel while the CPU runs in the user mode. It is disabled by
+//! default and can be enabled with the `ostd.kpti=on` kernel command line option.
+//!
+//! If KPTI is enabled, each CPU has its own user page table. The user half of the user page table
+//! is copied from the current page table right before the CPU enters the user mode, while the
+//! kernel half maps only the pages that the CPU accesses when it enters or exits the kernel:
+//!  - the entry code in the `.text.entry` section (i.e., `trap.S` and `syscall.S`);
+//!  - the IDT;
+//!  - the entry area of each CPU, which contains the GDT, the TSS, and the entry stack.
+//!
+//! The entry code switches to the kernel page table as soon as the CPU enters the kernel, and
+//! switches to the user page table right before the CPU returns to the user mode. The global pages
+//! are disabled, so that no kernel mapping survives in the TLB after the switches.
+//!
+//! This is intended for studying the side channels rather than for production use. It has the
+//! following limitations:
+//!  - The PCIDs are not used, so the TLB is flushed at every switch.
+//!  - NMIs that occur during the switches are not handled specially.
+//!  - It is not supported on other architectures.
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use log::info;
+use spin::Once;
+use x86_64::registers::control::Cr3;
+
+use super::{PageTableEntry, NR_ENTRIES_PER_PAGE};
+use crate::{
+    arch::trap::{gdt, idt},
+    boot::EARLY_INFO,
+    cpu::all_cpus,
+    mm::{
+        kspace::{kernel_loaded_offset, LINEAR_MAPPING_BASE_VADDR, LINEAR_MAPPING_VADDR_RANGE},
+        paddr_to_vaddr,
+        page_prop::{CachePolicy, PageFlags, PageProperty, PrivilegedPageFlags},
+        page_table::{KernelMode, PageTable},
+        Frame, FrameAllocOptions, Paddr, Vaddr, PAGE_SIZE,
+    },
+};
+
+static IS_ENABLED: Once<bool> = Once::new();
+
+/// The page table that maps the kernel half of the user page tables.
+static ENTRY_PAGE_TABLE: Once<PageTable<KernelMode>> = Once::new();
+
+/// The root frames of the user page tables of all CPUs.
+static USER_PAGE_TABLE_ROOTS: Once<Vec<Frame<()>>> = Once::new();
+
+/// Returns whether KPTI is enabled.
+pub(crate) fn is_enabled() -> bool {
+    *IS_ENABLED.call_once(|| {
+        let kcmdline = EARLY_INFO.get().unwrap().kernel_cmdline;
+
+        // Like the logger, we assume that the kernel command line follows the Linux kernel
+        // command line format, and search for the `ostd.kpti=on` argument.
+        kcmdline.split(' ').any(|arg| arg == "ostd.kpti=on")
+    })
+}
+
+/// Initializes the user page tables of all CPUs if KPTI is enabled.
+///
+/// This function should be called on the BSP after the IDT and the CPU-local storages are
+/// initialized, and before the APs are booted.
+pub(crate) fn init() {
+    if !is_enabled() {
+        return;
+    }
+
+    info!("Enabling the kernel page-table isolation");
+
+    let entry_pt = PageTable::<KernelMode>::new_kernel_page_table();
+    let map = |vaddr: Range<Vaddr>, flags: PageFlags| {
+        let paddr = kernel_vaddr_to_paddr(vaddr.start)..kernel_vaddr_to_paddr(vaddr.end);
+        let prop = PageProperty {
+            flags,
+            cache: CachePolicy::Writeback,
+            priv_flags: PrivilegedPageFlags::empty(),
+        };
+        // SAFETY: The page table is never activated in the kernel mode, and the pages are mapped
+        // at the same virtual addresses as the kernel page table does.
+        unsafe { entry_pt.map(&vaddr, &paddr, prop).unwrap() };
+    };
+
+    extern "C" {
+        fn __entry_text_start();
+        fn __entry_text_end();
+    }
+    map(
+        __entry_text_start as usize..__entry_text_end as usize,
+        PageFlags::RX,
+    );
+
+    let idt_vaddr = idt::idt_vaddr();
+    map(idt_vaddr..idt_vaddr + PAGE_SIZE, PageFlags::R);
+
+    for cpu in all_cpus() {
+        let area_vaddr = gdt::entry_area_vaddr(cpu);
+        map(area_vaddr..area_vaddr + PAGE_SIZE, PageFlags::RW);
+    }
+
+    // SAFETY: The entry page table lives for `'static` after being put in `ENTRY_PAGE_TABLE`.
+    let entry_root_paddr = unsafe { entry_pt.root_paddr() };
+    let user_roots = all_cpus()
+        .map(|cpu| {
+            let root = FrameAllocOptions::new().alloc_frame().unwrap();
+            // SAFETY: Both frames are root page tables, and the new one is not activated yet.
+            unsafe {
+                copy_root_entries(
+                    entry_root_paddr,
+                    root.start_paddr(),
+                    NR_ENTRIES_PER_PAGE / 2..NR_ENTRIES_PER_PAGE,
+                )
+            };
+            // SAFETY: The user page table maps the entry code and the entry area at the same
+            // virtual addresses as the kernel page table does. The CPU is not running in the user
+            // mode because the APs are not booted yet.
+            unsafe { gdt::set_user_cr3(cpu, root.start_paddr() as u64) };
+            root
+        })
+        .collect();
+
+    ENTRY_PAGE_TABLE.call_once(|| entry_pt);
+    USER_PAGE_TABLE_ROOTS.call_once(|| user_roots);
+}
+
+/// Synchronizes the user page table of the current CPU with the current page table.
+///
+/// This function should be called right before the current CPU enters the user mode, with the
+/// local IRQs disabled.
+pub(crate) fn sync_user_page_table() {
+    if !USER_PAGE_TABLE_ROOTS.is_completed() {
+        return;
+    }
+
+    let (frame, flags) = Cr3::read_raw();
+    let kernel_root_paddr = frame.start_address().as_u64() as Paddr;
+    let user_root_paddr = gdt::user_cr3() as Paddr;
+
+    // SAFETY: Both frames are root page tables. Only the user half of the user page table is
+    // updated, which is not in use because the current CPU runs in the kernel mode.
+    unsafe {
+        copy_root_entries(
+            kernel_root_paddr,
+            user_root_paddr,
+            0..NR_ENTRIES_PER_PAGE / 2,
+        )
+    };
+
+    // SAFETY: The current page table maps the kernel correctly, and the local IRQs are disabled
+    // (as upheld by the caller).
+    unsafe { gdt::set_kernel_cr3(kernel_root_paddr as u64 | flags as u64) };
+}
+
+/// Copies the entries in the range from a root page table to another.
+///
+/// # Safety
+///
+/// The caller must ensure that both physical addresses point to root page tables, and that
+/// overwriting the entries of the destination does not affect the memory safety.
+unsafe fn copy_root_entries(from: Paddr, to: Paddr, range: Range<usize>) {
+    let from = paddr_to_vaddr(from) as *const PageTableEntry;
+    let to = paddr_to_vaddr(to) as *mut PageTableEntry;
+
+    for i in range {
+        // SAFETY: The index is in the bounds of the page table (as upheld by the caller).
+        unsafe { to.add(i).write_volatile(from.add(i).read_volatile()) };
+    }
+}
+
+/// Converts the virtual address in the linear mapping or in the kernel image to the physical
+/// address.
+fn kernel_vaddr_to_paddr(vaddr: Vaddr) -> Paddr {
+    if LINEAR_MAPPING_VADDR_RANGE.contains(&vaddr) {
+        vaddr - LINEAR_MAPPING_BASE_VADDR
+    } else {
+        vaddr - kernel_loaded_offset()
+    }
+}
@@ -6,7 +6,7 @@ use alloc::fmt;
 use core::ops::Range;
 
 use cfg_if::cfg_if;
-pub(crate) use util::{__memcpy_fallible, __memset_fallible};
+pub(crate) use util::{__atomic_cmpxchg_fallible, __memcpy_fallible, __memset_fallible};
 use x86_64::{instructions::tlb, structures::paging::PhysFrame, VirtAddr};
 
 use crate::{
@@ -19,6 +19,7 @@ use crate::{
     Pod,
 };
 
+pub(crate) mod kpti;
 mod util;
 
 pub(crate) const NR_ENTRIES_PER_PAGE: usize = 512;
@@ -98,6 +99,15 @@ pub(crate) fn tlb_flush_all_excluding_global() {
 
 /// Flush all TLB entries, including global-page entries.
 pub(crate) fn tlb_flush_all_including_global() {
+    // If global-page extensions are disabled (e.g., with the kernel page-table
+    // isolation), there are no global-page entries in the TLB.
+    if !x86_64::registers::control::Cr4::read()
+        .contains(x86_64::registers::control::Cr4Flags::PAGE_GLOBAL)
+    {
+        tlb::flush_all();
+        return;
+    }
+
     // SAFETY: updates to CR4 here only change the global-page bit, the side effect
     // is only to invalidate the TLB, which doesn't affect the memory safety.
     unsafe {
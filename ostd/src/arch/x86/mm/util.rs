@@ -1,15 +1,70 @@
 // SPDX-License-Identifier: MPL-2.0
 
-core::arch::global_asm!(include_str!("memcpy_fallible.S"));
+use crate::arch::cpu::{feature::CpuFeature, smap::UserAccessGuard};
+
+core::arch::global_asm!(
+    include_str!("memcpy_fallible.S"),
+    FEATURE_ERMS = const CpuFeature::Erms as u16,
+);
 core::arch::global_asm!(include_str!("memset_fallible.S"));
+core::arch::global_asm!(include_str!("atomic_cmpxchg_fallible.S"));
 
 extern "C" {
-    /// Copies `size` bytes from `src` to `dst`. This function works with exception handling
-    /// and can recover from page fault.
-    /// Returns number of bytes that failed to copy.
-    pub(crate) fn __memcpy_fallible(dst: *mut u8, src: *const u8, size: usize) -> usize;
-    /// Fills `size` bytes in the memory pointed to by `dst` with the value `value`.
-    /// This function works with exception handling and can recover from page fault.
-    /// Returns number of bytes that failed to set.
-    pub(crate) fn __memset_fallible(dst: *mut u8, value: u8, size: usize) -> usize;
+    fn __memcpy_fallible_asm(dst: *mut u8, src: *const u8, size: usize) -> usize;
+    fn __memset_fallible_asm(dst: *mut u8, value: u8, size: usize) -> usize;
+    fn __atomic_cmpxchg_fallible_asm(ptr: *mut u32, old_val: u32, new_val: u32) -> u64;
+}
+
+/// Copies `size` bytes from `src` to `dst`. This function works with exception handling
+/// and can recover from page fault.
+/// Returns number of bytes that failed to copy.
+///
+/// The copy is done in a user-access window, so either range can be in the user space.
+///
+/// # Safety
+///
+/// The caller must ensure that the ranges are either valid or in the user space, as required
+/// by `memcpy_fallible` in `mm::io`.
+pub(crate) unsafe fn __memcpy_fallible(dst: *mut u8, src: *const u8, size: usize) -> usize {
+    let _guard = UserAccessGuard::new();
+    // SAFETY: The safety is upheld by the caller.
+    unsafe { __memcpy_fallible_asm(dst, src, size) }
+}
+
+/// Fills `size` bytes in the memory pointed to by `dst` with the value `value`.
+/// This function works with exception handling and can recover from page fault.
+/// Returns number of bytes that failed to set.
+///
+/// The memory is set in a user-access window, so the range can be in the user space.
+///
+/// # Safety
+///
+/// The caller must ensure that the range is either valid or in the user space, as required by
+/// `memset_fallible` in `mm::io`.
+pub(crate) unsafe fn __memset_fallible(dst: *mut u8, value: u8, size: usize) -> usize {
+    let _guard = UserAccessGuard::new();
+    // SAFETY: The safety is upheld by the caller.
+    unsafe { __memset_fallible_asm(dst, value, size) }
+}
+
+/// Atomically compares the `u32` value at `ptr` with `old_val` and replaces it with `new_val`
+/// if they are equal. This function works with exception handling and can recover from page
+/// fault.
+/// Returns the previous value, or `None` if a page fault cannot be resolved.
+///
+/// The access is done in a user-access window, so the value can be in the user space.
+///
+/// # Safety
+///
+/// The caller must ensure that `ptr` is aligned and either valid or in the user space, as
+/// required by `VmWriter::atomic_compare_exchange` in `mm::io`.
+pub(crate) unsafe fn __atomic_cmpxchg_fallible(
+    ptr: *mut u32,
+    old_val: u32,
+    new_val: u32,
+) -> Option<u32> {
+    let _guard = UserAccessGuard::new();
+    // SAFETY: The safety is upheld by the caller.
+    let prev_val = unsafe { __atomic_cmpxchg_fallible_asm(ptr, old_val, new_val) };
+    u32::try_from(prev_val).ok()
 }
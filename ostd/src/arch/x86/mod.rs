@@ -6,18 +6,21 @@ pub mod boot;
 pub(crate) mod cpu;
 pub mod device;
 pub(crate) mod ex_table;
+pub(crate) mod gdbstub;
 pub(crate) mod io;
 pub mod iommu;
 pub(crate) mod irq;
 pub(crate) mod kernel;
 pub(crate) mod mm;
 pub(crate) mod pci;
+pub(crate) mod power;
 pub mod qemu;
 pub mod serial;
 pub mod task;
 pub mod timer;
 pub mod trap;
 
+use cpu::feature::{has_cpu_feature, CpuFeature};
 use io::construct_io_mem_allocator_builder;
 use spin::Once;
 use x86::cpuid::{CpuId, FeatureInfo};
@@ -62,9 +65,15 @@ static CPU_FEATURES: Once<FeatureInfo> = Once::new();
 /// This function must be called only once in the boot context of the
 /// bootstrapping processor.
 pub(crate) unsafe fn late_init_on_bsp() {
+    // SAFETY: This function is only called once on BSP, with the local IRQs disabled and before
+    // the APs are booted. The CPU features have been detected in `enable_cpu_features`.
+    unsafe { cpu::alternative::apply_alternatives() };
+
     // SAFETY: This function is only called once on BSP.
     unsafe { trap::init() };
 
+    mm::kpti::init();
+
     kernel::acpi::init();
 
     let io_mem_builder = construct_io_mem_allocator_builder();
@@ -101,6 +110,8 @@ pub(crate) unsafe fn late_init_on_bsp() {
     // 2. All the port I/O regions belonging to the system device are defined using the macros.
     // 3. `MAX_IO_PORT` defined in `crate::arch::io` is the maximum value specified by x86-64.
     unsafe { crate::io::init(io_mem_builder) };
+
+    power::init();
 }
 
 /// Architecture-specific initialization on the application processor.
@@ -136,17 +147,27 @@ pub fn read_tsc() -> u64 {
 
 /// Reads a hardware generated 64-bit random value.
 ///
+/// The value is generated with `rdrand` by a cryptographically secure PRNG, which is reseeded by
+/// the hardware entropy source.
+///
 /// Returns None if no random value was generated.
 pub fn read_random() -> Option<u64> {
     use core::arch::x86_64::_rdrand64_step;
 
+    use self::cpu::feature::{has_cpu_feature, CpuFeature};
+
     // Recommendation from "Intel® Digital Random Number Generator (DRNG) Software
     // Implementation Guide" - Section 5.2.1 and "Intel® 64 and IA-32 Architectures
     // Software Developer’s Manual" - Volume 1 - Section 7.3.17.1.
     const RETRY_LIMIT: usize = 10;
 
+    if !has_cpu_feature(CpuFeature::Rdrand) {
+        return None;
+    }
+
     for _ in 0..RETRY_LIMIT {
         let mut val = 0;
+        // SAFETY: The CPU supports `rdrand`, as checked above.
         let generated = unsafe { _rdrand64_step(&mut val) };
         if generated == 1 {
             return Some(val);
@@ -155,6 +176,38 @@ pub fn read_random() -> Option<u64> {
     None
 }
 
+/// Reads a 64-bit random value from the hardware entropy source directly.
+///
+/// Unlike [`read_random`], the value is read with `rdseed` and is not generated by a PRNG, so it
+/// is suitable for seeding other PRNGs. The entropy source may be exhausted temporarily, so it is
+/// slower and more likely to fail.
+///
+/// Returns None if no random value was generated.
+pub fn read_random_seed() -> Option<u64> {
+    use core::arch::x86_64::_rdseed64_step;
+
+    use self::cpu::feature::{has_cpu_feature, CpuFeature};
+
+    // Recommendation from "Intel® Digital Random Number Generator (DRNG) Software
+    // Implementation Guide" - Section 5.3.1, which retries with a pause in between.
+    const RETRY_LIMIT: usize = 100;
+
+    if !has_cpu_feature(CpuFeature::Rdseed) {
+        return None;
+    }
+
+    for _ in 0..RETRY_LIMIT {
+        let mut val = 0;
+        // SAFETY: The CPU supports `rdseed`, as checked above.
+        let generated = unsafe { _rdseed64_step(&mut val) };
+        if generated == 1 {
+            return Some(val);
+        }
+        core::hint::spin_loop();
+    }
+    None
+}
+
 fn has_avx() -> bool {
     use core::arch::x86_64::{__cpuid, __cpuid_count};
 
@@ -191,14 +244,37 @@ pub(crate) fn enable_cpu_features() {
         cpuid.get_feature_info().unwrap()
     });
 
+    cpu::feature::init();
     cpu::context::enable_essential_features();
 
     let mut cr4 = x86_64::registers::control::Cr4::read();
-    cr4 |= Cr4Flags::FSGSBASE
-        | Cr4Flags::OSXSAVE
-        | Cr4Flags::OSFXSR
-        | Cr4Flags::OSXMMEXCPT_ENABLE
-        | Cr4Flags::PAGE_GLOBAL;
+    cr4 |= Cr4Flags::OSXSAVE | Cr4Flags::OSFXSR | Cr4Flags::OSXMMEXCPT_ENABLE;
+    // The global pages are disabled with the kernel page-table isolation, so that the kernel
+    // mappings do not survive in the TLB when the CPU enters the user mode.
+    if mm::kpti::is_enabled() {
+        cr4 -= Cr4Flags::PAGE_GLOBAL;
+    } else {
+        cr4 |= Cr4Flags::PAGE_GLOBAL;
+    }
+    // Enabling the `rdfsbase`, `wrfsbase`, `rdgsbase`, and `wrgsbase` instructions is safe as long
+    // as the kernel properly deals with the arbitrary base values set by the userspace program.
+    // See `cpu::fsgsbase` for details.
+    if cpu::fsgsbase::has_fsgsbase() {
+        cr4 |= Cr4Flags::FSGSBASE;
+    }
+    // Forbid the kernel from executing and accessing the user pages, except for the accesses in
+    // the user-access windows. See `cpu::smap` for details.
+    if has_cpu_feature(CpuFeature::Smep) {
+        cr4 |= Cr4Flags::SUPERVISOR_MODE_EXECUTION_PROTECTION;
+    }
+    if cpu::smap::has_smap() {
+        cr4 |= Cr4Flags::SUPERVISOR_MODE_ACCESS_PREVENTION;
+    }
+    // Forbid the user space from executing `sgdt`, `sidt`, `sldt`, `smsw`, and `str`, which
+    // leak the kernel addresses.
+    if has_cpu_feature(CpuFeature::Umip) {
+        cr4 |= Cr4Flags::USER_MODE_INSTRUCTION_PREVENTION;
+    }
     unsafe {
         x86_64::registers::control::Cr4::write(cr4);
     }
@@ -219,6 +295,9 @@ pub(crate) fn enable_cpu_features() {
         x86_64::registers::xcontrol::XCr0::write(xcr0);
     }
 
+    // The FPU configuration depends on the state components enabled in XCR0.
+    cpu::context::init_fpu();
+
     unsafe {
         // enable non-executable page protection
         x86_64::registers::model_specific::Efer::update(|efer| {
@@ -0,0 +1,313 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Power management via ACPI.
+//!
+//! The machine is powered off by entering the S5 (soft off) sleeping state and restarted via the
+//! reset register, both of which are described in the FADT. The power button events are reported
+//! by the system control interrupt (SCI).
+//!
+//! Only the PM1a registers in the I/O space are supported. The PM1b registers are rarely
+//! implemented.
+//!
+//! Reference: ACPI Specification 6.5, Sections 4.8 and 7.4.
+
+use acpi::{
+    address::{AddressSpace, GenericAddress},
+    fadt::Fadt,
+    InterruptModel,
+};
+use log::{error, info, warn};
+use spin::Once;
+use x86_64::instructions::port::Port;
+
+use super::{
+    device::io_port::{ReadWriteAccess, WriteOnlyAccess},
+    kernel::{
+        acpi::{get_acpi_tables, get_platform_info},
+        IO_APIC,
+    },
+    qemu::QemuExitCode,
+};
+use crate::{
+    io::{sensitive_io_port, IoPort},
+    mm::paddr_to_vaddr,
+    power::ExitCode,
+    trap::{IrqLine, TrapFrame},
+};
+
+/// The ACPI registers for power management.
+struct AcpiPmRegisters {
+    /// The PM1a status register.
+    pm1a_sts: IoPort<u16, ReadWriteAccess>,
+    /// The PM1a enable register.
+    pm1a_en: IoPort<u16, ReadWriteAccess>,
+    /// The PM1a control register.
+    pm1a_cnt: IoPort<u16, ReadWriteAccess>,
+    /// The value of `SLP_TYPa` for the S5 sleeping state.
+    s5_sleep_type: Option<u8>,
+    /// The reset register and the value to write into it.
+    reset: Option<(IoPort<u8, WriteOnlyAccess>, u8)>,
+}
+
+static PM_REGISTERS: Once<AcpiPmRegisters> = Once::new();
+
+static SCI_IRQ: Once<IrqLine> = Once::new();
+
+/// The power button status bit in the PM1 status register.
+const PWRBTN_STS: u16 = 1 << 8;
+/// The power button enable bit in the PM1 enable register.
+const PWRBTN_EN: u16 = 1 << 8;
+/// The SCI enable bit in the PM1 control register, which indicates whether ACPI is enabled.
+const SCI_EN: u16 = 1 << 0;
+/// The sleep type bits in the PM1 control register.
+const SLP_TYP_SHIFT: u16 = 10;
+const SLP_TYP_MASK: u16 = 0b111 << SLP_TYP_SHIFT;
+/// The sleep enable bit in the PM1 control register.
+const SLP_EN: u16 = 1 << 13;
+
+sensitive_io_port! {
+    unsafe {
+        /// The command port of the 8042 keyboard controller.
+        static I8042_COMMAND: IoPort<u8, WriteOnlyAccess> = IoPort::new(0x64);
+    }
+}
+
+/// The 8042 command that pulses the CPU reset line.
+const I8042_PULSE_RESET: u8 = 0xFE;
+
+/// Initializes the power management and enables the power button events.
+///
+/// This function must be called after the I/O port allocator and the I/O APICs are initialized.
+pub(super) fn init() {
+    let Some(acpi_tables) = get_acpi_tables() else {
+        return;
+    };
+    let Ok(fadt) = acpi_tables.find_table::<Fadt>() else {
+        warn!("[ACPI]: FADT is not found, power management is disabled");
+        return;
+    };
+
+    let (Some(pm1a_evt), Some(pm1a_cnt)) = (
+        fadt.pm1a_event_block().ok().and_then(io_port_number),
+        fadt.pm1a_control_block().ok().and_then(io_port_number),
+    ) else {
+        warn!("[ACPI]: PM1a registers are not available, power management is disabled");
+        return;
+    };
+    // The PM1 event block consists of the status register and the enable register, which have
+    // the same length.
+    let pm1_evt_len = fadt.pm1_event_length as u16;
+    let (Ok(pm1a_sts), Ok(pm1a_en), Ok(pm1a_cnt)) = (
+        IoPort::acquire(pm1a_evt),
+        IoPort::acquire(pm1a_evt + pm1_evt_len / 2),
+        IoPort::acquire(pm1a_cnt),
+    ) else {
+        warn!("[ACPI]: PM1a registers cannot be acquired, power management is disabled");
+        return;
+    };
+
+    let s5_sleep_type = acpi_tables.dsdt().ok().and_then(|dsdt| {
+        // SAFETY: The DSDT is part of the ACPI tables, which are mapped in the linear mapping.
+        // See also the safety comments in `AcpiMemoryHandler::map_physical_region`.
+        let aml = unsafe {
+            core::slice::from_raw_parts(
+                paddr_to_vaddr(dsdt.address) as *const u8,
+                dsdt.length as usize,
+            )
+        };
+        find_s5_sleep_type(aml)
+    });
+    if s5_sleep_type.is_none() {
+        warn!("[ACPI]: The S5 sleeping state is not found, powering off via ACPI is disabled");
+    }
+
+    let flags = fadt.flags;
+    let reset = if flags.supports_system_reset_via_fadt() {
+        let reset_value = fadt.reset_value;
+        fadt.reset_register()
+            .ok()
+            .and_then(io_port_number)
+            .and_then(|port| IoPort::acquire(port).ok())
+            .map(|port| (port, reset_value))
+    } else {
+        None
+    };
+
+    let registers = PM_REGISTERS.call_once(|| AcpiPmRegisters {
+        pm1a_sts,
+        pm1a_en,
+        pm1a_cnt,
+        s5_sleep_type,
+        reset,
+    });
+
+    let sci_interrupt = fadt.sci_interrupt;
+    let smi_cmd_port = fadt.smi_cmd_port;
+    let acpi_enable = fadt.acpi_enable;
+    if registers.pm1a_cnt.read() & SCI_EN == 0 {
+        // The system is in the legacy mode, so the ACPI events must be enabled first.
+        let Ok(smi_cmd_port) = u16::try_from(smi_cmd_port) else {
+            return;
+        };
+        if smi_cmd_port == 0 || acpi_enable == 0 {
+            return;
+        }
+        // SAFETY: Writing `ACPI_ENABLE` to the SMI command port is the way to transfer the
+        // ownership of the ACPI hardware registers to the OS, as specified in the FADT.
+        unsafe { Port::<u8>::new(smi_cmd_port).write(acpi_enable) };
+
+        const MAX_RETRIES: usize = 1_000_000;
+        if !(0..MAX_RETRIES).any(|_| registers.pm1a_cnt.read() & SCI_EN != 0) {
+            warn!("[ACPI]: Failed to enable ACPI, power button events are disabled");
+            return;
+        }
+    }
+
+    enable_power_button(registers, sci_interrupt);
+}
+
+fn io_port_number(address: GenericAddress) -> Option<u16> {
+    if !matches!(address.address_space, AddressSpace::SystemIo) || address.address == 0 {
+        return None;
+    }
+    u16::try_from(address.address).ok()
+}
+
+/// Finds the value of `SLP_TYPa` for the S5 sleeping state in the AML code.
+///
+/// There is no AML interpreter, so the `\_S5` object is located by searching for its encoding.
+/// This works for the firmware that defines `\_S5` as a package of constant integers, which is
+/// the common case (e.g., for QEMU).
+fn find_s5_sleep_type(aml: &[u8]) -> Option<u8> {
+    const NAME_OP: u8 = 0x08;
+    const ROOT_CHAR: u8 = b'\\';
+    const PACKAGE_OP: u8 = 0x12;
+    const ZERO_OP: u8 = 0x00;
+    const ONE_OP: u8 = 0x01;
+    const BYTE_PREFIX: u8 = 0x0A;
+
+    let pos = aml.windows(4).position(|name| name == b"_S5_")?;
+    let is_name = match pos {
+        0 => false,
+        1 => aml[0] == NAME_OP,
+        _ => aml[pos - 1] == NAME_OP || (aml[pos - 1] == ROOT_CHAR && aml[pos - 2] == NAME_OP),
+    };
+    if !is_name {
+        return None;
+    }
+
+    // The encoding is `PackageOp PkgLength NumElements PackageElementList`, where the bits 6-7
+    // of the first byte of `PkgLength` specify the number of the following bytes.
+    let package = aml.get(pos + 4..)?;
+    if *package.first()? != PACKAGE_OP {
+        return None;
+    }
+    let pkg_length_bytes = (*package.get(1)? >> 6) as usize + 1;
+    let elements = package.get(1 + pkg_length_bytes + 1..)?;
+
+    match *elements.first()? {
+        ZERO_OP => Some(0),
+        ONE_OP => Some(1),
+        BYTE_PREFIX => elements.get(1).copied(),
+        _ => None,
+    }
+}
+
+fn enable_power_button(registers: &AcpiPmRegisters, sci_interrupt: u16) {
+    // The SCI is specified as an ISA IRQ, which may be mapped to another GSI.
+    let gsi = match get_platform_info().map(|info| &info.interrupt_model) {
+        Some(InterruptModel::Apic(apic)) => apic
+            .interrupt_source_overrides
+            .iter()
+            .find(|over| over.isa_source as u16 == sci_interrupt)
+            .map_or(sci_interrupt as u32, |over| over.global_system_interrupt),
+        _ => sci_interrupt as u32,
+    };
+
+    let Ok(mut irq) = IrqLine::alloc() else {
+        warn!("[ACPI]: No IRQ lines for the SCI, power button events are disabled");
+        return;
+    };
+    irq.on_active(handle_sci);
+
+    let Some(io_apic) = IO_APIC.get().and_then(|io_apics| {
+        io_apics
+            .iter()
+            .filter(|io_apic| io_apic.lock().interrupt_base() <= gsi)
+            .max_by_key(|io_apic| io_apic.lock().interrupt_base())
+    }) else {
+        warn!("[ACPI]: No I/O APICs for the SCI, power button events are disabled");
+        return;
+    };
+    let mut io_apic = io_apic.lock();
+    let index = (gsi - io_apic.interrupt_base()) as u8;
+    if io_apic.enable(index, irq.clone()).is_err() {
+        warn!("[ACPI]: Failed to enable the SCI, power button events are disabled");
+        return;
+    }
+    drop(io_apic);
+    SCI_IRQ.call_once(|| irq);
+
+    // The status bit is cleared by writing one to it.
+    registers.pm1a_sts.write(PWRBTN_STS);
+    registers
+        .pm1a_en
+        .write(registers.pm1a_en.read() | PWRBTN_EN);
+
+    info!("[ACPI]: Power button events are enabled (GSI {})", gsi);
+}
+
+fn handle_sci(_trap_frame: &TrapFrame) {
+    let Some(registers) = PM_REGISTERS.get() else {
+        return;
+    };
+
+    if registers.pm1a_sts.read() & PWRBTN_STS != 0 {
+        // The status bit is cleared by writing one to it.
+        registers.pm1a_sts.write(PWRBTN_STS);
+        crate::power::notify_power_button_pressed();
+    }
+}
+
+pub(crate) fn poweroff(code: ExitCode) -> ! {
+    // If the kernel runs in QEMU with the ISA debug exit device, this exits QEMU with the exit
+    // code. Otherwise, this has no effect. See also `super::qemu::exit_qemu`.
+    let qemu_code = match code {
+        ExitCode::Success => QemuExitCode::Success,
+        ExitCode::Failure => QemuExitCode::Failed,
+    };
+    // SAFETY: The write to the ISA debug exit port is safe and `0xf4` should be the port number.
+    unsafe { Port::<u32>::new(0xf4).write(qemu_code as u32) };
+
+    if let Some(registers) = PM_REGISTERS.get()
+        && let Some(sleep_type) = registers.s5_sleep_type
+    {
+        let value = registers.pm1a_cnt.read() & !SLP_TYP_MASK;
+        registers
+            .pm1a_cnt
+            .write(value | ((sleep_type as u16) << SLP_TYP_SHIFT) | SLP_EN);
+    }
+
+    halt_forever("power off")
+}
+
+pub(crate) fn restart() -> ! {
+    if let Some(registers) = PM_REGISTERS.get()
+        && let Some((reset_port, reset_value)) = &registers.reset
+    {
+        reset_port.write(*reset_value);
+    }
+
+    I8042_COMMAND.write(I8042_PULSE_RESET);
+
+    halt_forever("restart")
+}
+
+fn halt_forever(action: &str) -> ! {
+    error!("Failed to {} the machine", action);
+
+    super::irq::disable_local();
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
@@ -34,3 +34,35 @@ pub fn exit_qemu(exit_code: QemuExitCode) -> ! {
     }
     unreachable!()
 }
+
+/// The I/O port of the QEMU debug console.
+///
+/// The debug console is specified with the QEMU command line arguments
+/// `-device isa-debugcon,iobase=0xe9,chardev=<ID>`.
+const DEBUGCON_PORT: u16 = 0xe9;
+
+/// Writes the bytes to the QEMU debug console.
+///
+/// Returns `false` without writing anything if the debug console does not
+/// exist.
+pub(crate) fn write_debugcon(bytes: &[u8]) -> bool {
+    use x86_64::instructions::port::Port;
+
+    // Port I/O is emulated by the untrusted host in TDX guests.
+    crate::arch::if_tdx_enabled!({
+        return false;
+    });
+
+    let mut port = Port::<u8>::new(DEBUGCON_PORT);
+    // SAFETY: Accessing the debug console port has no side effects other than
+    // the output. If the debug console exists, reading the port returns the
+    // port number.
+    if unsafe { port.read() } != DEBUGCON_PORT as u8 {
+        return false;
+    }
+    for &byte in bytes {
+        // SAFETY: Same as above.
+        unsafe { port.write(byte) };
+    }
+    true
+}
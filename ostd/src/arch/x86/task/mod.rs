@@ -2,7 +2,7 @@
 
 //! The architecture support of context switch.
 
-use crate::task::TaskContextApi;
+use crate::{arch::cpu::fsgsbase, task::TaskContextApi};
 
 core::arch::global_asm!(include_str!("switch.S"));
 
@@ -12,6 +12,8 @@ pub(crate) struct TaskContext {
     pub regs: CalleeRegs,
     pub rip: usize,
     pub fsbase: usize,
+    /// The GS base of the user space.
+    pub gsbase: usize,
 }
 
 impl TaskContext {
@@ -20,6 +22,7 @@ impl TaskContext {
             regs: CalleeRegs::new(),
             rip: 0,
             fsbase: 0,
+            gsbase: 0,
         }
     }
 
@@ -32,6 +35,11 @@ impl TaskContext {
     pub fn tls_pointer(&self) -> usize {
         self.fsbase
     }
+
+    /// Sets the GS base of the user space.
+    pub fn set_user_gsbase(&mut self, gsbase: usize) {
+        self.gsbase = gsbase;
+    }
 }
 
 /// Callee-saved registers.
@@ -88,5 +96,30 @@ impl TaskContextApi for TaskContext {
 }
 
 extern "C" {
-    pub(crate) fn context_switch(cur: *mut TaskContext, nxt: *const TaskContext);
+    fn context_switch_regs(cur: *mut TaskContext, nxt: *const TaskContext);
+}
+
+/// Switches from the current task context to the next task context.
+///
+/// The FS base and the user GS base are saved to the current context and restored from the next
+/// context, so that the values set by the user space (e.g., via `wrfsbase`) survive context
+/// switches.
+///
+/// # Safety
+///
+/// The caller must have exclusive access to both contexts, and the next context must be valid.
+/// Local IRQs must be disabled.
+pub(crate) unsafe fn context_switch(cur: *mut TaskContext, nxt: *const TaskContext) {
+    // SAFETY: The caller guarantees that both contexts can be accessed exclusively.
+    unsafe {
+        (*cur).fsbase = fsgsbase::read_fsbase();
+        (*cur).gsbase = fsgsbase::read_user_gsbase();
+
+        fsgsbase::write_fsbase((*nxt).fsbase);
+        fsgsbase::write_user_gsbase((*nxt).gsbase);
+    }
+
+    // SAFETY: The caller guarantees that both contexts can be accessed exclusively and the next
+    // context is valid.
+    unsafe { context_switch_regs(cur, nxt) };
 }
@@ -51,6 +51,35 @@ pub(super) fn timer_callback() {
     }
 }
 
+/// Stops the APIC timer on the current CPU.
+pub(super) fn stop_timer() {
+    use x86::msr::{wrmsr, IA32_TSC_DEADLINE};
+
+    match CONFIG.get().expect("ACPI timer config is not initialized") {
+        Config::DeadlineMode { .. } => {
+            // Writing zero disarms the timer.
+            unsafe { wrmsr(IA32_TSC_DEADLINE, 0) };
+        }
+        Config::PeriodicMode { .. } => {
+            let preempt_guard = disable_preempt();
+            let apic = apic::get_or_init(&preempt_guard as _);
+            apic.set_timer_init_count(0);
+        }
+    }
+}
+
+/// Restarts the APIC timer on the current CPU that is stopped by [`stop_timer`].
+pub(super) fn restart_timer() {
+    match CONFIG.get().expect("ACPI timer config is not initialized") {
+        Config::DeadlineMode { .. } => timer_callback(),
+        Config::PeriodicMode { init_count } => {
+            let preempt_guard = disable_preempt();
+            let apic = apic::get_or_init(&preempt_guard as _);
+            apic.set_timer_init_count(*init_count);
+        }
+    }
+}
+
 /// Determines if the current system supports tsc_deadline mode APIC timer
 fn is_tsc_deadline_mode_supported() -> bool {
     use x86::cpuid::cpuid;
@@ -5,15 +5,17 @@
 mod apic;
 mod hpet;
 pub(crate) mod pit;
+mod sample;
 
 use core::sync::atomic::Ordering;
 
 use spin::Once;
 
+pub use self::sample::{register_sample_callback, StackSample, MAX_SAMPLE_DEPTH};
 use crate::{
     arch::kernel,
     cpu::{CpuId, PinCurrentCpu},
-    timer::INTERRUPT_CALLBACKS,
+    timer::{INTERRUPT_CALLBACKS, IS_TICK_STOPPED},
     trap::{self, IrqLine, TrapFrame},
 };
 
@@ -60,17 +62,43 @@ pub(super) fn init_ap() {
     }
 }
 
-fn timer_callback(_: &TrapFrame) {
+/// Stops the timer interrupts on the current CPU.
+///
+/// Returns `false` if the timer cannot be stopped, i.e., the PIT is used, which is shared by all
+/// the CPUs.
+pub(crate) fn stop_local_timer() -> bool {
+    if !kernel::apic::exists() {
+        return false;
+    }
+    apic::stop_timer();
+    true
+}
+
+/// Restarts the timer interrupts on the current CPU that are stopped by [`stop_local_timer`].
+pub(crate) fn restart_local_timer() {
+    apic::restart_timer();
+}
+
+fn timer_callback(trap_frame: &TrapFrame) {
     let irq_guard = trap::disable_local();
     if irq_guard.current_cpu() == CpuId::bsp() {
         crate::timer::jiffies::ELAPSED.fetch_add(1, Ordering::SeqCst);
     }
 
+    sample::sample(trap_frame, irq_guard.current_cpu());
+
     let callbacks_guard = INTERRUPT_CALLBACKS.get_with(&irq_guard);
     for callback in callbacks_guard.borrow().iter() {
         (callback)();
     }
     drop(callbacks_guard);
 
-    apic::timer_callback();
+    // The next interrupt is not armed if the timer is stopped by the callbacks.
+    if !IS_TICK_STOPPED.get_with(&irq_guard).load(Ordering::Relaxed) {
+        apic::timer_callback();
+    }
+
+    if irq_guard.current_cpu() == CpuId::bsp() {
+        crate::arch::gdbstub::poll();
+    }
 }
@@ -0,0 +1,123 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Sampling the interrupted stacks at the timer interrupts.
+//!
+//! The stack is walked with the frame pointers, so the frames beyond the
+//! interrupted instruction are only available if the kernel is built with
+//! `-C force-frame-pointers=yes`. Otherwise, the walk stops at the first
+//! frame whose saved frame pointer is not on the kernel stack of the current
+//! task.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use spin::Once;
+
+use crate::{cpu::CpuId, task::Task, trap::TrapFrame};
+
+/// The maximum number of the frames in a [`StackSample`].
+pub const MAX_SAMPLE_DEPTH: usize = 32;
+
+/// A stack trace sampled at a timer interrupt.
+#[derive(Debug)]
+pub struct StackSample {
+    cpu: CpuId,
+    is_user: bool,
+    frames: [usize; MAX_SAMPLE_DEPTH],
+    len: usize,
+}
+
+impl StackSample {
+    /// Returns the CPU that is interrupted.
+    pub fn cpu(&self) -> CpuId {
+        self.cpu
+    }
+
+    /// Returns whether the user mode is interrupted.
+    ///
+    /// The user stacks are not walked, so [`Self::frames`] only contains the
+    /// interrupted instruction for the user mode.
+    pub fn is_user(&self) -> bool {
+        self.is_user
+    }
+
+    /// Returns the program counters of the frames, starting from the
+    /// interrupted instruction and followed by the return addresses.
+    pub fn frames(&self) -> &[usize] {
+        &self.frames[..self.len]
+    }
+}
+
+/// The maximum number of the registered callbacks.
+const MAX_SAMPLE_CALLBACKS: usize = 4;
+
+static SAMPLE_CALLBACKS: [Once<fn(&StackSample)>; MAX_SAMPLE_CALLBACKS] =
+    [const { Once::new() }; MAX_SAMPLE_CALLBACKS];
+
+static NR_SAMPLE_CALLBACKS: AtomicUsize = AtomicUsize::new(0);
+
+/// Registers a function that receives the stack samples.
+///
+/// The function is called in the timer interrupt on all CPUs, so it should
+/// be fast and must not sleep.
+///
+/// # Panics
+///
+/// This function panics if too many functions (i.e., more than four) are
+/// registered.
+pub fn register_sample_callback(func: fn(&StackSample)) {
+    let index = NR_SAMPLE_CALLBACKS.fetch_add(1, Ordering::Relaxed);
+    assert!(index < MAX_SAMPLE_CALLBACKS, "too many sample callbacks");
+    SAMPLE_CALLBACKS[index].call_once(|| func);
+}
+
+/// Samples the interrupted stack if a callback is registered.
+pub(super) fn sample(trap_frame: &TrapFrame, cpu: CpuId) {
+    if SAMPLE_CALLBACKS[0].get().is_none() {
+        return;
+    }
+
+    let mut sample = StackSample {
+        cpu,
+        is_user: trap_frame.cs & 0x3 != 0,
+        frames: [0; MAX_SAMPLE_DEPTH],
+        len: 1,
+    };
+    sample.frames[0] = trap_frame.rip;
+
+    if !sample.is_user {
+        if let Some(task) = Task::current() {
+            let stack = task.kernel_stack_range();
+            let mut frame_pointer = trap_frame.rbp;
+            while sample.len < MAX_SAMPLE_DEPTH
+                && frame_pointer % align_of::<usize>() == 0
+                && stack.start <= frame_pointer
+                && frame_pointer + 2 * size_of::<usize>() <= stack.end
+            {
+                // SAFETY: The saved frame pointer and the return address are
+                // on the kernel stack of the current task, which is mapped.
+                // The values may be garbage if the frame pointers are
+                // omitted, but they are only reported as numbers.
+                let (next_frame_pointer, return_addr) = unsafe {
+                    let ptr = frame_pointer as *const usize;
+                    (ptr.read_volatile(), ptr.add(1).read_volatile())
+                };
+                if return_addr == 0 {
+                    break;
+                }
+                sample.frames[sample.len] = return_addr;
+                sample.len += 1;
+
+                // The stack grows downwards, so the frame of the caller is at
+                // a higher address.
+                if next_frame_pointer <= frame_pointer {
+                    break;
+                }
+                frame_pointer = next_frame_pointer;
+            }
+        }
+    }
+
+    for callback in SAMPLE_CALLBACKS.iter().map_while(Once::get) {
+        callback(&sample);
+    }
+}
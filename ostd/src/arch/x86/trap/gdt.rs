@@ -2,7 +2,11 @@
 
 //! Configure the Global Descriptor Table (GDT).
 
-use alloc::boxed::Box;
+use core::{
+    cell::UnsafeCell,
+    mem::{offset_of, size_of},
+    ptr::{addr_of, addr_of_mut},
+};
 
 use x86_64::{
     instructions::tables::{lgdt, load_tss},
@@ -18,7 +22,10 @@ use x86_64::{
     PrivilegeLevel, VirtAddr,
 };
 
-use crate::cpu::local::CpuLocal;
+use crate::{
+    cpu::{local::CpuLocal, CpuId},
+    mm::{paddr_to_vaddr, Vaddr, PAGE_SIZE},
+};
 
 /// Initializes and loads the GDT and TSS.
 ///
@@ -31,12 +38,21 @@ use crate::cpu::local::CpuLocal;
 /// The caller must ensure that no preemption can occur during the method, otherwise we may
 /// accidentally load a wrong GDT and TSS that actually belongs to another CPU.
 pub(super) unsafe fn init() {
-    let tss_ptr = LOCAL_TSS.as_ptr();
+    let area = UnsafeCell::raw_get(ENTRY_AREA.as_ptr());
+
+    // Traps in the user mode switch to the entry stack. See `trap.S` for details.
+    let mut tss = TaskStateSegment::new();
+    tss.privilege_stack_table[0] = VirtAddr::new((area as usize + PAGE_SIZE) as u64);
+    // SAFETY: The entry area is CPU-local, and no one else is accessing it during the
+    // initialization (as upheld by the caller).
+    unsafe { (*area).tss = tss };
+    // SAFETY: The pointer is derived from a valid pointer to the entry area.
+    let tss_ptr = unsafe { addr_of!((*area).tss) };
 
     // FIXME: The segment limit in the descriptor created by `tss_segment_unchecked` does not
     // include the I/O port bitmap.
 
-    // SAFETY: As a CPU-local variable, the TSS lives for `'static`.
+    // SAFETY: As a part of a CPU-local variable, the TSS lives for `'static`.
     let tss_desc = unsafe { Descriptor::tss_segment_unchecked(tss_ptr) };
     let (tss0, tss1) = match tss_desc {
         Descriptor::SystemSegment(tss0, tss1) => (tss0, tss1),
@@ -47,11 +63,16 @@ pub(super) unsafe fn init() {
     // intended for switching to a new kernel CS.
     assert_eq!(CS::get_reg(), KERNEL_CS);
 
-    // Allocate a new GDT with 8 entries.
-    let gdt = Box::new([
+    // Set up a new GDT with 8 entries.
+    let gdt = [
         0, KCODE64, KDATA, /* UCODE32 (not used) */ 0, UDATA, UCODE64, tss0, tss1,
-    ]);
-    let gdt = &*Box::leak(gdt);
+    ];
+    // SAFETY: The entry area is CPU-local, and no one else is accessing it during the
+    // initialization (as upheld by the caller).
+    let gdt = unsafe {
+        (*area).gdt = gdt;
+        &(*area).gdt
+    };
     assert_eq!(gdt[KERNEL_CS.index() as usize], KCODE64);
     assert_eq!(gdt[KERNEL_SS.index() as usize], KDATA);
     assert_eq!(gdt[USER_CS.index() as usize], UCODE64);
@@ -63,7 +84,7 @@ pub(super) unsafe fn init() {
         base: VirtAddr::new(gdt.as_ptr().addr() as u64),
     };
     // SAFETY: The GDT is valid to load because:
-    //  - It lives for `'static`.
+    //  - As a part of a CPU-local variable, it lives for `'static`.
     //  - It contains correct entries at correct indexes: the kernel code/data segments, the user
     //    code/data segments, and the TSS segment.
     //  - Specifically, the TSS segment points to the CPU-local TSS of the current CPU.
@@ -87,20 +108,107 @@ pub(super) unsafe fn init() {
     unsafe { Star::write_raw(sysret.0, syscall.0) };
 }
 
+/// The CPU-local data that the CPU accesses when it enters or exits the kernel.
+///
+/// The area occupies exactly one page, so that it can be mapped alone in the user page tables if
+/// the kernel page-table isolation is enabled (see `arch::mm::kpti`). It contains:
+///  - the TSS, whose `sp0` points to the top of the entry stack, `sp1` temporarily stores the
+///    user stack pointer when entering the kernel, and `sp2` stores the kernel stack pointer
+///    while the CPU runs in the user mode;
+///  - the page tables to switch to when entering and exiting the kernel, where zero means that
+///    no switch is needed;
+///  - a scratch slot to save a register while switching the page table;
+///  - the GDT;
+///  - the entry stack, to which the CPU saves the user context when a trap occurs in the user
+///    mode, before the context is moved to the [`UserContext`](super::UserContext).
+///
+/// `trap.S` and `syscall.S` access these fields at fixed offsets from the GS base.
+#[repr(C, align(4096))]
+struct EntryArea {
+    tss: TaskStateSegment,
+    kernel_cr3: u64,
+    user_cr3: u64,
+    scratch: u64,
+    gdt: [u64; 8],
+    stack: [u8; ENTRY_STACK_SIZE],
+}
+
+/// The size of the entry stack, which fills the rest of the page after the other fields.
+const ENTRY_STACK_SIZE: usize = PAGE_SIZE - 192;
+
+const _: () = assert!(size_of::<EntryArea>() == PAGE_SIZE);
+const _: () = assert!(offset_of!(EntryArea, stack) + ENTRY_STACK_SIZE == PAGE_SIZE);
+const _: () = assert!(offset_of!(EntryArea, tss) == 0);
+const _: () = assert!(offset_of!(EntryArea, kernel_cr3) == 104);
+const _: () = assert!(offset_of!(EntryArea, user_cr3) == 112);
+const _: () = assert!(offset_of!(EntryArea, scratch) == 120);
+
 // The linker script makes sure that the `.cpu_local_tss` section is at the beginning of the area
 // that stores CPU-local variables. This is important because `trap.S` and `syscall.S` will assume
-// this and treat the beginning of the CPU-local area as a TSS for loading and saving the kernel
-// stack!
+// this and treat the beginning of the CPU-local area as the entry area for loading and saving the
+// kernel stack!
 //
 // No other special initialization is required because the kernel stack information is stored in
 // the TSS when we start the userspace program. See `syscall.S` for details.
 #[link_section = ".cpu_local_tss"]
-static LOCAL_TSS: CpuLocal<TaskStateSegment> = {
-    let tss = TaskStateSegment::new();
+static ENTRY_AREA: CpuLocal<UnsafeCell<EntryArea>> = {
+    let area = EntryArea {
+        tss: TaskStateSegment::new(),
+        kernel_cr3: 0,
+        user_cr3: 0,
+        scratch: 0,
+        gdt: [0; 8],
+        stack: [0; ENTRY_STACK_SIZE],
+    };
     // SAFETY: The `.cpu_local_tss` section is part of the CPU-local area.
-    unsafe { CpuLocal::__new(tss) }
+    unsafe { CpuLocal::__new(UnsafeCell::new(area)) }
 };
 
+/// Returns the virtual address of the entry area of the CPU.
+pub(in crate::arch) fn entry_area_vaddr(cpu: CpuId) -> Vaddr {
+    if cpu == CpuId::bsp() {
+        // The BSP uses the statically linked CPU-local storage.
+        &ENTRY_AREA as *const _ as Vaddr
+    } else {
+        // The entry area is at the beginning of the CPU-local storage.
+        paddr_to_vaddr(crate::cpu::local::get_ap(cpu))
+    }
+}
+
+/// Sets the page table to switch to when exiting to the user mode on the CPU.
+///
+/// # Safety
+///
+/// The caller must ensure that the CPU is not running in the user mode, and that the page table
+/// maps the entry code and the entry area of the CPU at the same virtual addresses as the kernel
+/// page table does.
+pub(in crate::arch) unsafe fn set_user_cr3(cpu: CpuId, cr3: u64) {
+    let area = entry_area_vaddr(cpu) as *mut EntryArea;
+    // SAFETY: The field is only read when the CPU exits to the user mode, which cannot happen as
+    // upheld by the caller.
+    unsafe { addr_of_mut!((*area).user_cr3).write_volatile(cr3) };
+}
+
+/// Returns the page table to switch to when exiting to the user mode on the current CPU.
+pub(in crate::arch) fn user_cr3() -> u64 {
+    let area = UnsafeCell::raw_get(ENTRY_AREA.as_ptr());
+    // SAFETY: The field is only written by `set_user_cr3` before the CPU runs in the user mode.
+    unsafe { addr_of!((*area).user_cr3).read_volatile() }
+}
+
+/// Sets the page table to switch to when entering the kernel on the current CPU.
+///
+/// # Safety
+///
+/// The caller must ensure that the page table maps the kernel correctly, and that the local IRQs
+/// are disabled until the CPU enters the user mode.
+pub(in crate::arch) unsafe fn set_kernel_cr3(cr3: u64) {
+    let area = UnsafeCell::raw_get(ENTRY_AREA.as_ptr());
+    // SAFETY: The field is only read when the CPU enters the kernel from the user mode, which
+    // cannot happen before the CPU enters the user mode.
+    unsafe { addr_of_mut!((*area).kernel_cr3).write_volatile(cr3) };
+}
+
 // Kernel code and data descriptors.
 //
 // These are the exact, unique values that satisfy the requirements of the `syscall` instruction.
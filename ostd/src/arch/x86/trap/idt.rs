@@ -12,6 +12,8 @@ use x86_64::{
     PrivilegeLevel, VirtAddr,
 };
 
+use crate::mm::{Vaddr, PAGE_SIZE};
+
 global_asm!(include_str!("trap.S"));
 
 const NUM_INTERRUPTS: usize = 256;
@@ -21,6 +23,15 @@ extern "C" {
     static VECTORS: [usize; NUM_INTERRUPTS];
 }
 
+/// The IDT.
+///
+/// It occupies exactly one page, so that it can be mapped alone in the user page tables if the
+/// kernel page-table isolation is enabled (see `arch::mm::kpti`).
+#[repr(C, align(4096))]
+struct Idt([Entry<()>; NUM_INTERRUPTS]);
+
+const _: () = assert!(core::mem::size_of::<Idt>() == PAGE_SIZE);
+
 static GLOBAL_IDT: Once<&'static [Entry<()>]> = Once::new();
 
 /// Initializes and loads the IDT.
@@ -30,7 +41,7 @@ static GLOBAL_IDT: Once<&'static [Entry<()>]> = Once::new();
 /// more than load the same IDT.
 pub(super) fn init() {
     let idt = *GLOBAL_IDT.call_once(|| {
-        let idt = Box::leak(Box::new([const { Entry::missing() }; NUM_INTERRUPTS]));
+        let idt = &mut Box::leak(Box::new(Idt([const { Entry::missing() }; NUM_INTERRUPTS]))).0;
 
         // SAFETY: The vector array is properly initialized, lives for `'static`, and will never be
         // mutated. So it's always fine to create an immutable borrow to it.
@@ -64,3 +75,12 @@ pub(super) fn init() {
     //    correct handler signatures.
     unsafe { lidt(&idtr) };
 }
+
+/// Returns the virtual address of the IDT.
+///
+/// # Panics
+///
+/// This function will panic if the IDT has not been initialized.
+pub(in crate::arch) fn idt_vaddr() -> Vaddr {
+    GLOBAL_IDT.get().unwrap().as_ptr().addr()
+}
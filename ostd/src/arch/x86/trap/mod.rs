@@ -17,18 +17,20 @@
 //! Handles trap.
 
 pub(super) mod gdt;
-mod idt;
+pub(super) mod idt;
 mod syscall;
 
 use align_ext::AlignExt;
 use cfg_if::cfg_if;
 use log::debug;
 use spin::Once;
+use x86_64::registers::rflags::RFlags;
 
 use super::ex_table::ExTable;
 use crate::{
     arch::{
-        if_tdx_enabled,
+        cpu::smap::{close_user_access, has_smap},
+        gdbstub, if_tdx_enabled,
         irq::{disable_local, enable_local},
     },
     cpu::context::{CpuException, CpuExceptionInfo, PageFaultErrorCode},
@@ -38,7 +40,7 @@ use crate::{
         page_prop::{CachePolicy, PageProperty},
         PageFlags, PrivilegedPageFlags as PrivFlags, MAX_USERSPACE_VADDR, PAGE_SIZE,
     },
-    trap::call_irq_callback_functions,
+    trap::{call_irq_callback_functions, handle_fatal_kernel_exception},
 };
 
 cfg_if! {
@@ -236,10 +238,13 @@ extern "sysv64" fn trap_handler(f: &mut TrapFrame) {
         }
     }
 
+    // The trap may occur in a user-access window. Close it, and it will be reopened when the
+    // `AC` flag is restored on return.
+    close_user_access();
+
     // The IRQ state before trapping. We need to ensure that the IRQ state
     // during exception handling is consistent with the state before the trap.
-    let was_irq_enabled =
-        f.rflags as u64 & x86_64::registers::rflags::RFlags::INTERRUPT_FLAG.bits() > 0;
+    let was_irq_enabled = f.rflags as u64 & RFlags::INTERRUPT_FLAG.bits() > 0;
 
     match CpuException::to_cpu_exception(f.trap_num as u16) {
         #[cfg(feature = "cvm_guest")]
@@ -259,12 +264,23 @@ extern "sysv64" fn trap_handler(f: &mut TrapFrame) {
             // The actual user space implementation should be responsible
             // for providing mechanism to treat the 0 virtual address.
             if (0..MAX_USERSPACE_VADDR).contains(&(page_fault_addr as usize)) {
+                check_user_access(f, page_fault_addr);
                 handle_user_page_fault(f, page_fault_addr);
             } else {
                 handle_kernel_page_fault(f, page_fault_addr);
             }
             disable_local_if(was_irq_enabled);
         }
+        // The breakpoints and the single-steps of the GDB stub.
+        Some(exception @ (CpuException::BREAKPOINT | CpuException::DEBUG))
+            if gdbstub::handle_exception(exception, f) => {}
+        Some(exception) if exception.typ().is_fatal_or_trap() => {
+            enable_local_if(was_irq_enabled);
+            handle_fatal_kernel_exception(format_args!(
+                "cannot handle kernel CPU exception: {:?}, trapframe: {:?}",
+                exception, f
+            ));
+        }
         Some(exception) => {
             enable_local_if(was_irq_enabled);
             panic!(
@@ -292,6 +308,19 @@ pub fn inject_user_page_fault_handler(
     USER_PAGE_FAULT_HANDLER.call_once(|| handler);
 }
 
+/// Checks that the kernel accesses the user space in a user-access window.
+///
+/// Otherwise, the page fault is caused by SMAP and cannot be resolved.
+fn check_user_access(f: &TrapFrame, page_fault_addr: u64) {
+    let is_in_window = f.rflags as u64 & RFlags::ALIGNMENT_CHECK.bits() != 0;
+    if has_smap() && !is_in_window {
+        handle_fatal_kernel_exception(format_args!(
+            "Cannot access user space at {:#x} outside a user-access window; Trapframe:{:#x?}.",
+            page_fault_addr, f
+        ));
+    }
+}
+
 /// Handles page fault from user space.
 fn handle_user_page_fault(f: &mut TrapFrame, page_fault_addr: u64) {
     let info = CpuExceptionInfo {
@@ -315,7 +344,10 @@ fn handle_user_page_fault(f: &mut TrapFrame, page_fault_addr: u64) {
     if let Some(addr) = ExTable::find_recovery_inst_addr(f.rip) {
         f.rip = addr;
     } else {
-        panic!("Cannot handle user page fault; Trapframe:{:#x?}.", f);
+        handle_fatal_kernel_exception(format_args!(
+            "Cannot handle user page fault; Trapframe:{:#x?}.",
+            f
+        ));
     }
 }
 
@@ -328,10 +360,13 @@ fn handle_kernel_page_fault(f: &TrapFrame, page_fault_vaddr: u64) {
         page_fault_vaddr as *const (), error_code
     );
 
-    assert!(
-        LINEAR_MAPPING_VADDR_RANGE.contains(&(page_fault_vaddr as usize)),
-        "kernel page fault: the address is outside the range of the linear mapping",
-    );
+    if !LINEAR_MAPPING_VADDR_RANGE.contains(&(page_fault_vaddr as usize)) {
+        handle_fatal_kernel_exception(format_args!(
+            "kernel page fault: the address {:#x} is outside the range of the linear mapping; \
+             Trapframe:{:#x?}.",
+            page_fault_vaddr, f
+        ));
+    }
 
     const SUPPORTED_ERROR_CODES: PageFaultErrorCode = PageFaultErrorCode::PRESENT
         .union(PageFaultErrorCode::WRITE)
@@ -21,7 +21,6 @@ use core::arch::global_asm;
 use x86::cpuid::CpuId;
 use x86_64::{
     registers::{
-        control::{Cr4, Cr4Flags},
         model_specific::{Efer, EferFlags, LStar, SFMask},
         rflags::RFlags,
     },
@@ -47,7 +46,6 @@ pub(super) unsafe fn init() {
         .get_extended_processor_and_feature_identifiers()
         .unwrap()
         .has_syscall_sysret());
-    assert!(cpuid.get_extended_feature_info().unwrap().has_fsgsbase());
 
     // Flags to clear on syscall.
     //
@@ -67,15 +65,6 @@ pub(super) unsafe fn init() {
             efer.insert(EferFlags::SYSTEM_CALL_EXTENSIONS);
         });
     }
-
-    // SAFETY: Enabling the `rdfsbase`, `wrfsbase`, `rdgsbase`, and `wrgsbase` instructions is safe
-    // as long as the kernel properly deals with the arbitrary base values set by the userspace
-    // program. (FIXME: Do we really need to unconditionally enable them?)
-    unsafe {
-        Cr4::update(|cr4| {
-            cr4.insert(Cr4Flags::FSGSBASE);
-        })
-    };
 }
 
 extern "sysv64" {
@@ -113,8 +102,17 @@ impl UserContext {
     /// println!("back from user: {:#x?}", context);
     /// ```
     pub fn run(&mut self) {
+        // The local IRQs are disabled by `syscall_return` anyway. Disable them earlier so that
+        // the page tables do not change before entering the user mode.
+        crate::arch::irq::disable_local();
+        crate::arch::mm::kpti::sync_user_page_table();
+
         unsafe {
             syscall_return(self);
         }
+
+        // The user space may set the `AC` flag, which is kept if the CPU enters the kernel via an
+        // interrupt or an exception.
+        crate::arch::cpu::smap::close_user_access();
     }
 }
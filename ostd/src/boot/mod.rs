@@ -90,6 +90,7 @@ pub(crate) struct EarlyBootInfo {
 }
 
 /// The boot-time information.
+#[link_section = ".ro_after_init"]
 pub(crate) static EARLY_INFO: Once<EarlyBootInfo> = Once::new();
 
 /// Initializes the boot information.
@@ -121,6 +122,9 @@ pub(crate) fn call_ostd_main() -> ! {
         fn __ostd_main() -> !;
     }
 
+    // This function never returns, so it is safe to change the canary here.
+    crate::panic::init_stack_protector();
+
     // SAFETY: The function is called only once on the BSP.
     unsafe { crate::init() };
 
@@ -14,7 +14,7 @@ use crate::{
         common_device::PciCommonDevice,
         device_info::PciDeviceLocation,
     },
-    mm::VmIoOnce,
+    io::{ReadWrite, Register},
     trap::IrqLine,
 };
 
@@ -97,18 +97,11 @@ impl CapabilityMsixData {
         // Set message address 0xFEE0_0000
         for i in 0..table_size {
             // Set message address and disable this msix entry
-            table_bar
-                .io_mem()
-                .write_once((16 * i) as usize + table_offset, &message_address)
-                .unwrap();
-            table_bar
-                .io_mem()
-                .write_once((16 * i + 4) as usize + table_offset, &message_upper_address)
-                .unwrap();
-            table_bar
-                .io_mem()
-                .write_once((16 * i + 12) as usize + table_offset, &1_u32)
-                .unwrap();
+            table_entry(&table_bar, table_offset, i, MsixEntryField::MsgAddr)
+                .write(message_address);
+            table_entry(&table_bar, table_offset, i, MsixEntryField::MsgUpperAddr)
+                .write(message_upper_address);
+            table_entry(&table_bar, table_offset, i, MsixEntryField::VectorControl).write(1);
         }
 
         // enable MSI-X, bit15: MSI-X Enable
@@ -150,30 +143,15 @@ impl CapabilityMsixData {
         if let Some(remapping_index) = irq.remapping_index() {
             let address = construct_remappable_msix_address(remapping_index as u32);
 
-            self.table_bar
-                .io_mem()
-                .write_once((16 * index) as usize + self.table_offset, &address)
-                .unwrap();
-            self.table_bar
-                .io_mem()
-                .write_once((16 * index + 8) as usize + self.table_offset, &0)
-                .unwrap();
+            self.table_entry(index, MsixEntryField::MsgAddr).write(address);
+            self.table_entry(index, MsixEntryField::MsgData).write(0);
         } else {
-            self.table_bar
-                .io_mem()
-                .write_once(
-                    (16 * index + 8) as usize + self.table_offset,
-                    &(irq.num() as u32),
-                )
-                .unwrap();
+            self.table_entry(index, MsixEntryField::MsgData).write(irq.num() as u32);
         }
 
         let _old_irq = core::mem::replace(&mut self.irqs[index as usize], Some(irq));
         // Enable this msix vector
-        self.table_bar
-            .io_mem()
-            .write_once((16 * index + 12) as usize + self.table_offset, &0_u32)
-            .unwrap();
+        self.table_entry(index, MsixEntryField::VectorControl).write(0);
     }
 
     /// Gets mutable IrqLine. User can register callbacks by using this function.
@@ -186,6 +164,33 @@ impl CapabilityMsixData {
         let msg_ctrl = self.loc.read16(self.ptr + 2);
         msg_ctrl & 0x8000 != 0
     }
+
+    fn table_entry(&self, index: u16, field: MsixEntryField) -> Register<'_, u32, ReadWrite> {
+        table_entry(&self.table_bar, self.table_offset, index, field)
+    }
+}
+
+/// A field in an MSI-X table entry, whose value is the offset of the field in the entry.
+#[derive(Debug, Clone, Copy)]
+#[repr(usize)]
+enum MsixEntryField {
+    MsgAddr = 0,
+    MsgUpperAddr = 4,
+    MsgData = 8,
+    VectorControl = 12,
+}
+
+/// Returns the register of the field in the `index`-th entry of the MSI-X table.
+fn table_entry(
+    table_bar: &MemoryBar,
+    table_offset: usize,
+    index: u16,
+    field: MsixEntryField,
+) -> Register<'_, u32, ReadWrite> {
+    const ENTRY_SIZE: usize = 16;
+
+    let offset = table_offset + ENTRY_SIZE * index as usize + field as usize;
+    Register::new(table_bar.io_mem(), offset)
 }
 
 fn set_bit(origin_value: u16, offset: usize, set: bool) -> u16 {
@@ -64,6 +64,16 @@ impl PciCommonDevice {
         )
     }
 
+    /// Checks whether the device is still present.
+    ///
+    /// If the device has been removed (e.g., by surprise removal), reading its configuration
+    /// space returns all ones.
+    pub fn is_present(&self) -> bool {
+        self.location
+            .read16(PciDeviceCommonCfgOffset::VendorId as u16)
+            != 0xFFFF
+    }
+
     pub(super) fn new(location: PciDeviceLocation) -> Option<Self> {
         if location.read16(0) == 0xFFFF {
             // not exists
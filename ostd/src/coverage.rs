@@ -0,0 +1,341 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The code coverage of the kernel.
+//!
+//! When the kernel is built by `cargo osdk fuzz`, the compiler instruments
+//! every edge of the control flow graph with SanitizerCoverage's inline
+//! boolean flags. Each edge has a flag in the `__sancov_bools` section, which
+//! is set when the edge is executed for the first time. No callback is
+//! involved, so the flags can be set anywhere in the kernel, including in the
+//! interrupt handlers and in the code that runs before OSTD is initialized.
+//!
+//! The fuzzer compares the number of the covered edges before and after
+//! running an input to know whether the input reaches new code. If the kernel
+//! is not instrumented, the section is empty and no edges are reported.
+//!
+//! # Per-task PC traces
+//!
+//! When the kernel is built by `cargo osdk build --kcov`, the compiler calls
+//! `__sanitizer_cov_trace_pc_guard` on every edge instead. If a [`PcTrace`]
+//! is enabled for the current task, the PC of the edge is appended to the
+//! trace, unless the CPU is handling an interrupt. The trace is shared with
+//! the user space in the layout of Linux's kcov, so the tools built for kcov
+//! (e.g., syzkaller) can read it directly: the first word is the number of
+//! the PCs, which are stored in the following words.
+//!
+//! Reference: <https://clang.llvm.org/docs/SanitizerCoverage.html>
+
+use core::{
+    intrinsics::{volatile_load, volatile_store},
+    ptr::addr_of,
+    sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering},
+};
+
+use crate::{
+    cpu_local_cell,
+    mm::{paddr_to_vaddr, UFrame, PAGE_SIZE},
+    prelude::*,
+    task::Task,
+    trap::{self, INTERRUPT_NESTED_LEVEL},
+    Error,
+};
+
+/// Returns the number of the instrumented edges.
+pub fn nr_edges() -> usize {
+    flags().len()
+}
+
+/// Returns the number of the edges that have been executed since the boot.
+pub fn nr_covered_edges() -> usize {
+    flags()
+        .iter()
+        .filter(|flag| flag.load(Ordering::Relaxed) != 0)
+        .count()
+}
+
+fn flags() -> &'static [AtomicU8] {
+    extern "C" {
+        fn __start___sancov_bools();
+        fn __stop___sancov_bools();
+    }
+
+    let start = __start___sancov_bools as usize;
+    let len = __stop___sancov_bools as usize - start;
+    // SAFETY: The section is defined by the linker script, so it is valid for
+    // the lifetime of the kernel. The flags are written by the instrumented
+    // code concurrently, so they are accessed as atomic integers, which have
+    // the same layout as the boolean flags.
+    unsafe { core::slice::from_raw_parts(start as *const AtomicU8, len) }
+}
+
+/// Registers the flags of a module.
+///
+/// The instrumented modules call this function in their constructors with the
+/// bounds of the section. Since the bounds are defined by the linker script
+/// (see [`flags`]), nothing needs to be done here. But the symbol must exist,
+/// or the instrumented kernel cannot be linked.
+#[no_mangle]
+extern "C" fn __sanitizer_cov_bool_flag_init(_start: *const u8, _stop: *const u8) {}
+
+/// A trace of the PCs executed by a task.
+///
+/// The trace is stored in the frames given by the user, which are usually
+/// mapped to the user space as well.
+#[derive(Debug)]
+pub struct PcTrace {
+    /// The kernel virtual addresses of the frames.
+    pages: Box<[Vaddr]>,
+    /// The number of the words in the trace, including the counter.
+    capacity: usize,
+    is_enabled: AtomicBool,
+    _frames: Vec<UFrame>,
+}
+
+const WORD_SIZE: usize = size_of::<usize>();
+const WORDS_PER_PAGE: usize = PAGE_SIZE / WORD_SIZE;
+const GUARD_SIZE: usize = size_of::<u32>();
+
+impl PcTrace {
+    /// Creates a trace of `capacity` words in the frames.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the capacity is less than two words, or the
+    /// frames cannot hold the words.
+    pub fn new(frames: Vec<UFrame>, capacity: usize) -> Arc<Self> {
+        assert!(capacity >= 2);
+        assert!(frames.len() * WORDS_PER_PAGE >= capacity);
+
+        let pages = frames
+            .iter()
+            .map(|frame| paddr_to_vaddr(frame.start_paddr()))
+            .collect();
+        Arc::new(Self {
+            pages,
+            capacity,
+            is_enabled: AtomicBool::new(false),
+            _frames: frames,
+        })
+    }
+
+    /// Returns whether the trace is enabled for a task.
+    pub fn is_enabled(&self) -> bool {
+        self.is_enabled.load(Ordering::Relaxed)
+    }
+
+    /// Enables the trace for the current task.
+    ///
+    /// A trace can be enabled for one task at a time, and a task can have one
+    /// trace at a time. Otherwise, this method fails with
+    /// [`Error::AccessDenied`].
+    pub fn enable(self: &Arc<Self>) -> Result<()> {
+        let current = Task::current().ok_or(Error::InvalidArgs)?;
+
+        if self
+            .is_enabled
+            .compare_exchange(false, true, Ordering::Relaxed, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(Error::AccessDenied);
+        }
+
+        let ptr = Arc::into_raw(self.clone()) as usize;
+        let irq_guard = trap::disable_local();
+        if current
+            .pc_trace()
+            .0
+            .compare_exchange(0, ptr, Ordering::Relaxed, Ordering::Relaxed)
+            .is_err()
+        {
+            drop(irq_guard);
+            // SAFETY: The pointer is created by `Arc::into_raw` above and has
+            // not been published.
+            drop(unsafe { Arc::from_raw(ptr as *const Self) });
+            self.is_enabled.store(false, Ordering::Relaxed);
+            return Err(Error::AccessDenied);
+        }
+        CURRENT_PC_TRACE.store(ptr);
+        IS_TRACING.store(true, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Disables the trace for the current task.
+    ///
+    /// This method fails with [`Error::InvalidArgs`] if the trace is not
+    /// enabled for the current task.
+    pub fn disable(&self) -> Result<()> {
+        let current = Task::current().ok_or(Error::InvalidArgs)?;
+
+        if current.pc_trace().0.load(Ordering::Relaxed) != self as *const Self as usize {
+            return Err(Error::InvalidArgs);
+        }
+        disable_current(&current);
+
+        Ok(())
+    }
+}
+
+/// The PC trace enabled for a task.
+///
+/// The trace is stored as a pointer casted from [`Arc::into_raw`], or zero if
+/// there is no trace, so that it can be read cheaply in the context switches.
+#[derive(Debug, Default)]
+pub(crate) struct TaskPcTrace(AtomicUsize);
+
+impl Drop for TaskPcTrace {
+    fn drop(&mut self) {
+        let ptr = *self.0.get_mut();
+        if ptr != 0 {
+            // SAFETY: The pointer is created by `Arc::into_raw` in
+            // `PcTrace::enable`, and the task is no longer running.
+            let trace = unsafe { Arc::from_raw(ptr as *const PcTrace) };
+            trace.is_enabled.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Disables the PC trace of the current task, if any.
+pub(crate) fn disable_current(current: &Task) {
+    let irq_guard = trap::disable_local();
+    CURRENT_PC_TRACE.store(0);
+    let ptr = current.pc_trace().0.swap(0, Ordering::Relaxed);
+    drop(irq_guard);
+
+    if ptr != 0 {
+        // SAFETY: The pointer is created by `Arc::into_raw` in
+        // `PcTrace::enable`, and it is no longer used by the hook.
+        let trace = unsafe { Arc::from_raw(ptr as *const PcTrace) };
+        trace.is_enabled.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Switches the PC trace of the CPU to that of the next task.
+///
+/// This function must be called with the local IRQs disabled.
+pub(crate) fn switch_to(next_task: &Task) {
+    CURRENT_PC_TRACE.store(next_task.pc_trace().0.load(Ordering::Relaxed));
+}
+
+/// Whether any PC trace has been enabled.
+///
+/// The hook reads nothing else until a trace is enabled, since the CPU-local
+/// storage is not ready in the early boot.
+static IS_TRACING: AtomicBool = AtomicBool::new(false);
+
+cpu_local_cell! {
+    /// The PC trace of the current task, i.e., a pointer to the [`PcTrace`]
+    /// casted to a `usize`, or zero if there is no trace.
+    static CURRENT_PC_TRACE: usize = 0;
+}
+
+extern "C" {
+    fn __cpu_local_start();
+    fn __start___sancov_guards();
+    fn __start___sancov_pcs();
+}
+
+/// Loads a CPU-local cell in the hook.
+///
+/// [`CpuLocalCell::load`] cannot be used in the hook, since it is neither
+/// inlined in the debug builds nor exempt from the instrumentation.
+///
+/// [`CpuLocalCell::load`]: crate::cpu::local::CpuLocalCell::load
+macro_rules! load_cpu_local {
+    ($cell:expr, $x86_instr:literal, $riscv_instr:literal) => {{
+        let offset = addr_of!($cell) as usize - __cpu_local_start as usize;
+        let val: usize;
+        #[cfg(target_arch = "x86_64")]
+        core::arch::asm!(
+            $x86_instr,
+            out(reg) val,
+            in(reg) offset,
+            options(nostack, readonly, preserves_flags),
+        );
+        #[cfg(target_arch = "riscv64")]
+        core::arch::asm!(
+            "add {0}, gp, {1}",
+            $riscv_instr,
+            out(reg) val,
+            in(reg) offset,
+            options(nostack, readonly, preserves_flags),
+        );
+        val
+    }};
+}
+
+/// Records the PC of an edge.
+///
+/// The instrumented code calls this function on every edge with a unique
+/// guard of the edge. The PC of the edge is found in the PC table with the
+/// index of the guard, since the two sections are in the same order.
+///
+/// The compiler does not instrument this function, but the functions that it
+/// calls would be instrumented and call it recursively. So it calls no
+/// function, not even the inlined ones, which are not inlined in the debug
+/// builds.
+///
+/// # Safety
+///
+/// The guard must be in the `__sancov_guards` section.
+#[no_mangle]
+unsafe extern "C" fn __sanitizer_cov_trace_pc_guard(guard: *const u32) {
+    // SAFETY: The flag is a static variable, and `AtomicBool` has the same
+    // layout as `bool`.
+    if !unsafe { volatile_load(addr_of!(IS_TRACING) as *const bool) } {
+        return;
+    }
+
+    // SAFETY: The CPU-local storage is ready once a trace is enabled.
+    let (trace, irq_level) = unsafe {
+        (
+            load_cpu_local!(CURRENT_PC_TRACE, "mov {0}, gs:[{1}]", "ld {0}, 0({0})"),
+            load_cpu_local!(
+                INTERRUPT_NESTED_LEVEL,
+                "movzx {0:e}, byte ptr gs:[{1}]",
+                "lbu {0}, 0({0})"
+            ),
+        )
+    };
+    if trace == 0 || irq_level != 0 {
+        return;
+    }
+    let trace = trace as *const PcTrace;
+
+    // SAFETY: The trace is alive while it is enabled for the current task.
+    // The words are in the frames of the trace. The user space may write to
+    // the words concurrently, so they are accessed with volatile operations
+    // and the counter is checked before it is used.
+    unsafe {
+        let pages = addr_of!(*(*trace).pages) as *const Vaddr;
+        let counter = *pages as *mut usize;
+        let count = volatile_load(counter);
+        if count >= (*trace).capacity - 1 {
+            return;
+        }
+
+        let index = (guard as usize - __start___sancov_guards as usize) / GUARD_SIZE;
+        // Each entry of the PC table has a PC and the flags of the edge.
+        let pc = *((__start___sancov_pcs as usize + index * 2 * WORD_SIZE) as *const usize);
+
+        let pos = count + 1;
+        let page = *((pages as usize + pos / WORDS_PER_PAGE * WORD_SIZE) as *const Vaddr);
+        volatile_store((page + pos % WORDS_PER_PAGE * WORD_SIZE) as *mut usize, pc);
+        volatile_store(counter, pos);
+    }
+}
+
+/// Registers the guards of a module.
+///
+/// The guards need no initialization, since their indexes are computed from
+/// their addresses. But the symbol must exist, as with
+/// [`__sanitizer_cov_bool_flag_init`].
+#[no_mangle]
+extern "C" fn __sanitizer_cov_trace_pc_guard_init(_start: *mut u32, _stop: *mut u32) {}
+
+/// Registers the PC table of a module.
+///
+/// The table is found with the bounds defined by the linker script, so
+/// nothing needs to be done here either.
+#[no_mangle]
+extern "C" fn __sanitizer_cov_pcs_init(_start: *const usize, _stop: *const usize) {}
@@ -0,0 +1,75 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The kernel API exported to dynamic components.
+//!
+//! The Rust items of OSTD are not exported because their symbol names and
+//! layouts are not stable. Instead, dynamic components call the functions here
+//! through the C ABI, e.g.,
+//!
+//! ```ignore
+//! extern "C" {
+//!     fn ostd_log(level: usize, msg: *const u8, len: usize);
+//! }
+//! ```
+
+use core::{alloc::Layout, ptr};
+
+use crate::mm::paddr_to_vaddr;
+
+/// Prints a message to the kernel log.
+///
+/// The levels from 1 to 5 are error, warn, info, debug, and trace,
+/// respectively. Messages of other levels are printed at the info level.
+///
+/// # Safety
+///
+/// `msg` must point to `len` bytes of valid UTF-8.
+pub unsafe extern "C" fn ostd_log(level: usize, msg: *const u8, len: usize) {
+    let level = match level {
+        1 => log::Level::Error,
+        2 => log::Level::Warn,
+        4 => log::Level::Debug,
+        5 => log::Level::Trace,
+        _ => log::Level::Info,
+    };
+    // SAFETY: The safety is upheld by the caller.
+    let msg = unsafe { core::str::from_utf8_unchecked(core::slice::from_raw_parts(msg, len)) };
+    log::log!(level, "{}", msg);
+}
+crate::export_symbol!(ostd_log);
+
+/// Allocates memory from the kernel heap.
+///
+/// This function returns a null pointer if the allocation fails, `size` is
+/// zero, or `align` is not a power of two.
+pub extern "C" fn ostd_alloc(size: usize, align: usize) -> *mut u8 {
+    match Layout::from_size_align(size, align) {
+        // SAFETY: The layout has a non-zero size.
+        Ok(layout) if layout.size() != 0 => unsafe { alloc::alloc::alloc(layout) },
+        _ => ptr::null_mut(),
+    }
+}
+crate::export_symbol!(ostd_alloc);
+
+/// Frees memory allocated by [`ostd_alloc`].
+///
+/// # Safety
+///
+/// `ptr` must be returned by [`ostd_alloc`] with the same `size` and `align`,
+/// and must not be used afterwards.
+pub unsafe extern "C" fn ostd_dealloc(ptr: *mut u8, size: usize, align: usize) {
+    // SAFETY: The safety is upheld by the caller.
+    unsafe {
+        alloc::alloc::dealloc(ptr, Layout::from_size_align_unchecked(size, align));
+    }
+}
+crate::export_symbol!(ostd_dealloc);
+
+/// Returns the virtual address of a physical address in the linear mapping.
+///
+/// The access is controlled because it allows the component to access any
+/// physical memory, which should be limited to trusted device drivers.
+pub extern "C" fn ostd_paddr_to_vaddr(paddr: usize) -> usize {
+    paddr_to_vaddr(paddr)
+}
+crate::export_symbol!(controlled ostd_paddr_to_vaddr);
@@ -0,0 +1,325 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The parsing of position-independent ELF images.
+//!
+//! Only the little-endian ELF64 shared objects (`ET_DYN`) with `RELA`
+//! relocations are supported.
+
+use core::ops::Range;
+
+use ostd_pod::Pod;
+
+use crate::{prelude::*, Error};
+
+#[cfg(target_arch = "x86_64")]
+pub(super) const EM_CURRENT: u16 = 62; // EM_X86_64
+#[cfg(target_arch = "riscv64")]
+pub(super) const EM_CURRENT: u16 = 243; // EM_RISCV
+
+const ET_DYN: u16 = 3;
+
+pub(super) const PT_LOAD: u32 = 1;
+const PT_DYNAMIC: u32 = 2;
+const PT_TLS: u32 = 7;
+pub(super) const PT_GNU_RELRO: u32 = 0x6474_e552;
+
+pub(super) const PF_X: u32 = 1;
+pub(super) const PF_W: u32 = 2;
+
+const SHT_DYNSYM: u32 = 11;
+
+const DT_NULL: i64 = 0;
+const DT_NEEDED: i64 = 1;
+const DT_PLTRELSZ: i64 = 2;
+const DT_STRTAB: i64 = 5;
+const DT_SYMTAB: i64 = 6;
+const DT_RELA: i64 = 7;
+const DT_RELASZ: i64 = 8;
+const DT_STRSZ: i64 = 10;
+const DT_REL: i64 = 17;
+const DT_JMPREL: i64 = 23;
+
+pub(super) const SHN_UNDEF: u16 = 0;
+pub(super) const STB_WEAK: u8 = 2;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct ElfHeader {
+    ident: [u8; 16],
+    type_: u16,
+    machine: u16,
+    version: u32,
+    entry: u64,
+    phoff: u64,
+    shoff: u64,
+    flags: u32,
+    ehsize: u16,
+    phentsize: u16,
+    phnum: u16,
+    shentsize: u16,
+    shnum: u16,
+    shstrndx: u16,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct ProgramHeader {
+    pub(super) type_: u32,
+    pub(super) flags: u32,
+    pub(super) offset: u64,
+    pub(super) vaddr: u64,
+    pub(super) paddr: u64,
+    pub(super) filesz: u64,
+    pub(super) memsz: u64,
+    pub(super) align: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct SectionHeader {
+    name: u32,
+    type_: u32,
+    flags: u64,
+    addr: u64,
+    offset: u64,
+    size: u64,
+    link: u32,
+    info: u32,
+    addralign: u64,
+    entsize: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct DynamicEntry {
+    tag: i64,
+    val: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct Rela {
+    pub(super) offset: u64,
+    pub(super) info: u64,
+    pub(super) addend: i64,
+}
+
+impl Rela {
+    pub(super) fn symbol_index(&self) -> usize {
+        (self.info >> 32) as usize
+    }
+
+    pub(super) fn type_(&self) -> u32 {
+        self.info as u32
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct Symbol {
+    name: u32,
+    info: u8,
+    other: u8,
+    pub(super) shndx: u16,
+    pub(super) value: u64,
+    size: u64,
+}
+
+impl Symbol {
+    pub(super) fn binding(&self) -> u8 {
+        self.info >> 4
+    }
+}
+
+/// A parsed position-independent ELF image.
+pub(super) struct ElfImage<'a> {
+    bytes: &'a [u8],
+    pub(super) program_headers: Vec<ProgramHeader>,
+    /// The virtual address range spanned by all the loadable segments.
+    pub(super) span: Range<usize>,
+    dynsym: Range<usize>,
+    strtab: Range<usize>,
+    pub(super) relas: Vec<Rela>,
+}
+
+impl<'a> ElfImage<'a> {
+    pub(super) fn parse(bytes: &'a [u8]) -> Result<Self> {
+        let header: ElfHeader = read_pod(bytes, 0)?;
+        if header.ident[..4] != *b"\x7fELF"
+            // ELFCLASS64 and ELFDATA2LSB
+            || header.ident[4] != 2
+            || header.ident[5] != 1
+            || header.type_ != ET_DYN
+            || header.machine != EM_CURRENT
+            || header.phentsize as usize != size_of::<ProgramHeader>()
+        {
+            return Err(Error::InvalidArgs);
+        }
+
+        let program_headers = (0..header.phnum as usize)
+            .map(|i| {
+                read_pod(
+                    bytes,
+                    header.phoff as usize + i * size_of::<ProgramHeader>(),
+                )
+            })
+            .collect::<Result<Vec<ProgramHeader>>>()?;
+
+        let mut span = usize::MAX..0;
+        for phdr in program_headers.iter() {
+            match phdr.type_ {
+                PT_LOAD => {
+                    if phdr.filesz > phdr.memsz
+                        || bytes.len() < (phdr.offset as usize).saturating_add(phdr.filesz as usize)
+                    {
+                        return Err(Error::InvalidArgs);
+                    }
+                    let end = (phdr.vaddr as usize)
+                        .checked_add(phdr.memsz as usize)
+                        .ok_or(Error::Overflow)?;
+                    span.start = span.start.min(phdr.vaddr as usize);
+                    span.end = span.end.max(end);
+                }
+                // Thread-local storage is not supported in the kernel.
+                PT_TLS => return Err(Error::InvalidArgs),
+                _ => {}
+            }
+        }
+        // The image must be linked at address zero to be position-independent.
+        if span.start != 0 || span.end == 0 {
+            return Err(Error::InvalidArgs);
+        }
+
+        let mut image = Self {
+            bytes,
+            program_headers,
+            span,
+            dynsym: 0..0,
+            strtab: 0..0,
+            relas: Vec::new(),
+        };
+        image.parse_dynamic(&header)?;
+
+        Ok(image)
+    }
+
+    fn parse_dynamic(&mut self, header: &ElfHeader) -> Result<()> {
+        let Some(dynamic) = self
+            .program_headers
+            .iter()
+            .find(|phdr| phdr.type_ == PT_DYNAMIC)
+        else {
+            // Nothing to relocate.
+            return Ok(());
+        };
+
+        let (mut symtab, mut strtab, mut strsz) = (0, 0, 0);
+        let (mut rela, mut relasz, mut jmprel, mut pltrelsz) = (0, 0, 0, 0);
+        let nr_entries = dynamic.filesz as usize / size_of::<DynamicEntry>();
+        for i in 0..nr_entries {
+            let entry: DynamicEntry = read_pod(
+                self.bytes,
+                dynamic.offset as usize + i * size_of::<DynamicEntry>(),
+            )?;
+            let val = entry.val as usize;
+            match entry.tag {
+                DT_NULL => break,
+                // Dynamic components can only depend on the kernel.
+                DT_NEEDED | DT_REL => return Err(Error::InvalidArgs),
+                DT_SYMTAB => symtab = val,
+                DT_STRTAB => strtab = val,
+                DT_STRSZ => strsz = val,
+                DT_RELA => rela = val,
+                DT_RELASZ => relasz = val,
+                DT_JMPREL => jmprel = val,
+                DT_PLTRELSZ => pltrelsz = val,
+                _ => {}
+            }
+        }
+
+        for (vaddr, size) in [(rela, relasz), (jmprel, pltrelsz)] {
+            if size == 0 {
+                continue;
+            }
+            let offset = self.vaddr_to_offset(vaddr)?;
+            for i in 0..size / size_of::<Rela>() {
+                self.relas
+                    .push(read_pod(self.bytes, offset + i * size_of::<Rela>())?);
+            }
+        }
+
+        if symtab != 0 {
+            // The number of dynamic symbols is only recorded in the section header.
+            let nr_symbols = (0..header.shnum as usize)
+                .map(|i| {
+                    read_pod::<SectionHeader>(
+                        self.bytes,
+                        header.shoff as usize + i * size_of::<SectionHeader>(),
+                    )
+                })
+                .find(|shdr| shdr.as_ref().is_ok_and(|shdr| shdr.type_ == SHT_DYNSYM))
+                .ok_or(Error::InvalidArgs)??
+                .size as usize
+                / size_of::<Symbol>();
+            let start = self.vaddr_to_offset(symtab)?;
+            self.dynsym = start..start + nr_symbols * size_of::<Symbol>();
+            let start = self.vaddr_to_offset(strtab)?;
+            self.strtab = start..start + strsz;
+            if self.dynsym.end > self.bytes.len() || self.strtab.end > self.bytes.len() {
+                return Err(Error::InvalidArgs);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the content of the segment in the file.
+    pub(super) fn segment_data(&self, phdr: &ProgramHeader) -> &'a [u8] {
+        let offset = phdr.offset as usize;
+        &self.bytes[offset..offset + phdr.filesz as usize]
+    }
+
+    /// Returns the dynamic symbol at the index.
+    pub(super) fn symbol(&self, index: usize) -> Result<Symbol> {
+        let offset = self.dynsym.start + index * size_of::<Symbol>();
+        if offset + size_of::<Symbol>() > self.dynsym.end {
+            return Err(Error::InvalidArgs);
+        }
+        read_pod(self.bytes, offset)
+    }
+
+    /// Returns the name of the dynamic symbol.
+    pub(super) fn symbol_name(&self, symbol: &Symbol) -> Result<&'a str> {
+        let strtab = &self.bytes[self.strtab.clone()];
+        let name = strtab
+            .get(symbol.name as usize..)
+            .and_then(|s| s.split(|b| *b == 0).next())
+            .ok_or(Error::InvalidArgs)?;
+        core::str::from_utf8(name).map_err(|_| Error::InvalidArgs)
+    }
+
+    /// Returns an iterator over all the dynamic symbols.
+    pub(super) fn symbols(&self) -> impl Iterator<Item = Symbol> + '_ {
+        let nr_symbols = self.dynsym.len() / size_of::<Symbol>();
+        (0..nr_symbols).filter_map(|index| self.symbol(index).ok())
+    }
+
+    fn vaddr_to_offset(&self, vaddr: usize) -> Result<usize> {
+        self.program_headers
+            .iter()
+            .filter(|phdr| phdr.type_ == PT_LOAD)
+            .find(|phdr| {
+                (phdr.vaddr..phdr.vaddr.saturating_add(phdr.filesz)).contains(&(vaddr as u64))
+            })
+            .map(|phdr| vaddr - phdr.vaddr as usize + phdr.offset as usize)
+            .ok_or(Error::InvalidArgs)
+    }
+}
+
+fn read_pod<T: Pod>(bytes: &[u8], offset: usize) -> Result<T> {
+    let bytes = offset
+        .checked_add(size_of::<T>())
+        .and_then(|end| bytes.get(offset..end))
+        .ok_or(Error::InvalidArgs)?;
+    Ok(T::from_bytes(bytes))
+}
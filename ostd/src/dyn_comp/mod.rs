@@ -0,0 +1,474 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Dynamic components.
+//!
+//! A dynamic component is a kernel extension that is loaded at runtime, which
+//! allows developing out-of-tree drivers without rebuilding the whole kernel.
+//! It is a position-independent ELF shared object, e.g., a Rust `cdylib`
+//! built with `-C relocation-model=pic` for the same target, the same
+//! toolchain, and the same version of OSTD as the kernel.
+//!
+//! Upon loading, the component is mapped into the kernel address space with
+//! the permissions of its segments and is relocated. The symbols that it does
+//! not define are resolved against the symbols exported by the kernel with
+//! [`export_symbol!`], e.g., the functions in [`api`]. The access to
+//! controlled symbols is checked at load time, as the component system does
+//! for the statically linked components.
+//!
+//! [`export_symbol!`]: crate::export_symbol
+
+pub mod api;
+mod elf;
+mod symbol;
+
+use alloc::{collections::btree_map::BTreeMap, string::String, vec};
+
+use align_ext::AlignExt;
+
+use self::elf::{ElfImage, Rela, PF_W, PF_X, PT_GNU_RELRO, PT_LOAD, SHN_UNDEF, STB_WEAK};
+pub use self::symbol::{exported_symbols, ExportedSymbol};
+use crate::{
+    mm::{
+        kspace::kvirt_area::{KVirtArea, Tracked},
+        CachePolicy, FrameAllocOptions, PageFlags, PageProperty, PrivilegedPageFlags, Segment,
+        VmIo, PAGE_SIZE,
+    },
+    prelude::*,
+    Error,
+};
+
+/// Options for loading a dynamic component.
+#[derive(Debug, Default)]
+pub struct LoadOptions {
+    allowed_controlled: Vec<&'static str>,
+}
+
+impl LoadOptions {
+    /// Creates new options for loading a dynamic component.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows the component to access the controlled kernel symbol.
+    pub fn allow_controlled(&mut self, name: &'static str) -> &mut Self {
+        self.allowed_controlled.push(name);
+        self
+    }
+
+    /// Loads a dynamic component from its ELF image.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Error::InvalidArgs`] if the image is malformed or
+    /// uses unsupported features, e.g., it has undefined symbols that are not
+    /// exported by the kernel. It returns [`Error::AccessDenied`] if the
+    /// image refers to a controlled symbol that is not allowed.
+    ///
+    /// # Safety
+    ///
+    /// The code in the image runs with the kernel privilege. The caller must
+    /// ensure that the image is trusted and is built against this kernel, and
+    /// that nothing in the component is used after the returned
+    /// [`DynComponent`] is dropped.
+    pub unsafe fn load(&self, image: &[u8]) -> Result<DynComponent> {
+        let elf = ElfImage::parse(image)?;
+
+        let size = elf.span.end.align_up(PAGE_SIZE);
+        let nr_pages = size / PAGE_SIZE;
+        let segment = FrameAllocOptions::new().alloc_segment(nr_pages)?;
+        for phdr in elf.program_headers.iter().filter(|p| p.type_ == PT_LOAD) {
+            segment.write_bytes(phdr.vaddr as usize, elf.segment_data(phdr))?;
+        }
+
+        let flags = page_flags(&elf, nr_pages)?;
+        let area = KVirtArea::<Tracked>::map_pages_with_props(
+            size,
+            0,
+            segment.clone().zip(flags).map(|(page, flags)| {
+                let prop = PageProperty {
+                    flags,
+                    cache: CachePolicy::Writeback,
+                    priv_flags: PrivilegedPageFlags::GLOBAL,
+                };
+                (page, prop)
+            }),
+        );
+        let base = area.start();
+
+        for rela in elf.relas.iter() {
+            self.relocate(&elf, &segment, base, rela)?;
+        }
+        #[cfg(target_arch = "riscv64")]
+        // SAFETY: Synchronizing the instruction stream has no side effects.
+        unsafe {
+            core::arch::asm!("fence.i");
+        }
+
+        let mut symbols = BTreeMap::new();
+        for sym in elf.symbols() {
+            if sym.shndx != SHN_UNDEF {
+                let name = elf.symbol_name(&sym)?;
+                symbols.insert(String::from(name), base + sym.value as usize);
+            }
+        }
+
+        Ok(DynComponent {
+            _area: area,
+            symbols,
+        })
+    }
+
+    fn relocate(
+        &self,
+        elf: &ElfImage,
+        segment: &Segment<()>,
+        base: Vaddr,
+        rela: &Rela,
+    ) -> Result<()> {
+        let symbol_value = || -> Result<usize> {
+            let sym = elf.symbol(rela.symbol_index())?;
+            if sym.shndx != SHN_UNDEF {
+                return Ok(base + sym.value as usize);
+            }
+            let name = elf.symbol_name(&sym)?;
+            match symbol::find_exported_symbol(name) {
+                Some(exported) => {
+                    if exported.is_controlled() && !self.allowed_controlled.contains(&name) {
+                        log::warn!("Access to the controlled symbol `{}` is denied", name);
+                        return Err(Error::AccessDenied);
+                    }
+                    Ok(exported.addr())
+                }
+                None if sym.binding() == STB_WEAK => Ok(0),
+                None => {
+                    log::warn!("The symbol `{}` is not exported by the kernel", name);
+                    Err(Error::InvalidArgs)
+                }
+            }
+        };
+
+        let value = match rela.type_() {
+            reloc::NONE => return Ok(()),
+            reloc::RELATIVE => base.wrapping_add_signed(rela.addend as isize),
+            reloc::ABS64 => symbol_value()?.wrapping_add_signed(rela.addend as isize),
+            type_ if reloc::SYMBOL.contains(&type_) => symbol_value()?,
+            type_ => {
+                log::warn!("Unsupported relocation type: {}", type_);
+                return Err(Error::InvalidArgs);
+            }
+        };
+        segment.write_val(rela.offset as usize, &(value as u64))
+    }
+}
+
+/// Returns the page flags of each page in the image.
+///
+/// A page shared by multiple segments gets the permissions of all of them,
+/// which must not be both writable and executable.
+fn page_flags(elf: &ElfImage, nr_pages: usize) -> Result<Vec<PageFlags>> {
+    let mut flags = vec![PageFlags::empty(); nr_pages];
+
+    let pages_of = |vaddr: u64, memsz: u64| {
+        let start = (vaddr as usize).align_down(PAGE_SIZE) / PAGE_SIZE;
+        let end = (vaddr as usize + memsz as usize).align_up(PAGE_SIZE) / PAGE_SIZE;
+        start..end
+    };
+
+    for phdr in elf.program_headers.iter().filter(|p| p.type_ == PT_LOAD) {
+        let mut segment_flags = PageFlags::R;
+        if phdr.flags & PF_W != 0 {
+            segment_flags |= PageFlags::W;
+        }
+        if phdr.flags & PF_X != 0 {
+            segment_flags |= PageFlags::X;
+        }
+        for page in pages_of(phdr.vaddr, phdr.memsz) {
+            flags[page] |= segment_flags;
+            if flags[page].contains(PageFlags::W | PageFlags::X) {
+                return Err(Error::InvalidArgs);
+            }
+        }
+    }
+
+    // The relocated data are written via the linear mapping, so the pages
+    // that are read-only after relocation can be mapped as read-only now.
+    for phdr in elf
+        .program_headers
+        .iter()
+        .filter(|p| p.type_ == PT_GNU_RELRO)
+    {
+        // Only the pages fully covered by the RELRO segment can be protected.
+        let start = (phdr.vaddr as usize).align_up(PAGE_SIZE) / PAGE_SIZE;
+        let end = (phdr.vaddr as usize + phdr.memsz as usize).align_down(PAGE_SIZE) / PAGE_SIZE;
+        for page in start..end.min(nr_pages) {
+            flags[page].remove(PageFlags::W);
+        }
+    }
+
+    // The gaps between segments are mapped but never accessed.
+    for page_flags in flags.iter_mut().filter(|f| f.is_empty()) {
+        *page_flags = PageFlags::R;
+    }
+
+    Ok(flags)
+}
+
+/// A loaded dynamic component.
+///
+/// The component is unloaded when this object is dropped.
+#[derive(Debug)]
+pub struct DynComponent {
+    _area: KVirtArea<Tracked>,
+    symbols: BTreeMap<String, Vaddr>,
+}
+
+impl DynComponent {
+    /// Returns the address of a symbol defined in the component.
+    ///
+    /// The caller may cast the address to the expected type of the symbol,
+    /// e.g., the entry function of the component.
+    pub fn symbol(&self, name: &str) -> Option<Vaddr> {
+        self.symbols.get(name).copied()
+    }
+}
+
+/// The supported relocation types.
+#[cfg(target_arch = "x86_64")]
+mod reloc {
+    pub(super) const NONE: u32 = 0;
+    /// `S + A`.
+    pub(super) const ABS64: u32 = 1;
+    /// `B + A`.
+    pub(super) const RELATIVE: u32 = 8;
+    /// `S`, i.e., `R_X86_64_GLOB_DAT` and `R_X86_64_JUMP_SLOT`.
+    pub(super) const SYMBOL: &[u32] = &[6, 7];
+}
+
+/// The supported relocation types.
+#[cfg(target_arch = "riscv64")]
+mod reloc {
+    pub(super) const NONE: u32 = 0;
+    /// `S + A`. It is also used for GOT entries.
+    pub(super) const ABS64: u32 = 2;
+    /// `B + A`.
+    pub(super) const RELATIVE: u32 = 3;
+    /// `S`, i.e., `R_RISCV_JUMP_SLOT`.
+    pub(super) const SYMBOL: &[u32] = &[5];
+}
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+
+    #[ktest]
+    fn reject_malformed_image() {
+        let options = LoadOptions::new();
+        // SAFETY: The image is rejected before being loaded.
+        let res = unsafe { options.load(b"\x7fELF not really an image") };
+        assert_eq!(res.err(), Some(Error::InvalidArgs));
+    }
+
+    #[ktest]
+    fn load_and_relocate() {
+        let image = build_image("ostd_alloc");
+        // SAFETY: The component contains no code.
+        let component = unsafe { LoadOptions::new().load(&image) }.unwrap();
+
+        let slots = component.symbol("slots").unwrap();
+        let (relative, import) = read_slots(slots);
+        assert_eq!(relative, slots + 8);
+        assert_eq!(import, api::ostd_alloc as usize);
+    }
+
+    #[ktest]
+    fn check_controlled_symbol() {
+        let image = build_image("ostd_paddr_to_vaddr");
+        // SAFETY: The component contains no code.
+        let res = unsafe { LoadOptions::new().load(&image) };
+        assert_eq!(res.err(), Some(Error::AccessDenied));
+
+        // SAFETY: The component contains no code.
+        let component = unsafe {
+            LoadOptions::new()
+                .allow_controlled("ostd_paddr_to_vaddr")
+                .load(&image)
+        }
+        .unwrap();
+        let (_, import) = read_slots(component.symbol("slots").unwrap());
+        assert_eq!(import, api::ostd_paddr_to_vaddr as usize);
+    }
+
+    fn read_slots(slots: Vaddr) -> (usize, usize) {
+        // SAFETY: The slots are in the writable page of the loaded component.
+        unsafe { (*(slots as *const usize), *((slots + 8) as *const usize)) }
+    }
+
+    /// Builds a minimal dynamic component with a read-only page for the
+    /// metadata and a writable page for the data.
+    ///
+    /// The data are two slots exported as `slots`. The first slot is relocated
+    /// to the address of the second one by a `RELATIVE` relocation, and the
+    /// second slot is relocated to the address of the imported kernel symbol.
+    fn build_image(import: &str) -> Vec<u8> {
+        const DYNSYM: usize = 0x100;
+        const DYNSTR: usize = 0x180;
+        const RELA: usize = 0x200;
+        const DYNAMIC: usize = 0x240;
+        const SHDRS: usize = 0x300;
+        const DATA: usize = PAGE_SIZE;
+        const DATA_LEN: u64 = 16;
+
+        let mut image = vec![0u8; DATA + DATA_LEN as usize];
+        let mut dynstr = vec![0u8];
+        dynstr.extend_from_slice(import.as_bytes());
+        dynstr.push(0);
+        let slots_name = dynstr.len() as u32;
+        dynstr.extend_from_slice(b"slots\0");
+
+        // The ELF header: `ET_DYN`, three program headers and three section headers.
+        let machine = elf::EM_CURRENT.to_le_bytes();
+        write(
+            &mut image,
+            0,
+            &[
+                b"\x7fELF\x02\x01\x01",
+                &[0; 9],
+                &3u16.to_le_bytes(),
+                &machine,
+                &1u32.to_le_bytes(),
+                &0u64.to_le_bytes(),
+                &64u64.to_le_bytes(),
+                &(SHDRS as u64).to_le_bytes(),
+                &0u32.to_le_bytes(),
+                &64u16.to_le_bytes(),
+                &56u16.to_le_bytes(),
+                &3u16.to_le_bytes(),
+                &64u16.to_le_bytes(),
+                &3u16.to_le_bytes(),
+                &0u16.to_le_bytes(),
+            ],
+        );
+
+        // The program headers.
+        let dynamic_len = 6 * 16;
+        for (i, (type_, flags, offset, len, align)) in [
+            (PT_LOAD, 4, 0, DATA as u64, PAGE_SIZE as u64),
+            (PT_LOAD, 4 | PF_W, DATA as u64, DATA_LEN, PAGE_SIZE as u64),
+            (2, 4, DYNAMIC as u64, dynamic_len, 8),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            write(
+                &mut image,
+                64 + i * 56,
+                &[
+                    &type_.to_le_bytes(),
+                    &flags.to_le_bytes(),
+                    &offset.to_le_bytes(),
+                    &offset.to_le_bytes(),
+                    &offset.to_le_bytes(),
+                    &len.to_le_bytes(),
+                    &len.to_le_bytes(),
+                    &align.to_le_bytes(),
+                ],
+            );
+        }
+
+        // The dynamic symbols: the null symbol, the import, and `slots`.
+        for (i, (name, info, shndx, value)) in [
+            (0, 0u8, SHN_UNDEF, 0),
+            (1, 0x12, SHN_UNDEF, 0),
+            (slots_name, 0x11, 2u16, DATA as u64),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            write(
+                &mut image,
+                DYNSYM + i * 24,
+                &[
+                    &name.to_le_bytes(),
+                    &[info, 0],
+                    &shndx.to_le_bytes(),
+                    &value.to_le_bytes(),
+                    &0u64.to_le_bytes(),
+                ],
+            );
+        }
+        write(&mut image, DYNSTR, &[&dynstr]);
+
+        // The relocations.
+        for (i, (offset, symbol, type_, addend)) in [
+            (DATA as u64, 0u64, reloc::RELATIVE, DATA as u64 + 8),
+            (DATA as u64 + 8, 1, reloc::ABS64, 0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            write(
+                &mut image,
+                RELA + i * 24,
+                &[
+                    &offset.to_le_bytes(),
+                    &((symbol << 32) | type_ as u64).to_le_bytes(),
+                    &addend.to_le_bytes(),
+                ],
+            );
+        }
+
+        // The dynamic section: `DT_SYMTAB`, `DT_STRTAB`, `DT_STRSZ`, `DT_RELA`,
+        // `DT_RELASZ`, and `DT_NULL`.
+        for (i, (tag, val)) in [
+            (6u64, DYNSYM as u64),
+            (5, DYNSTR as u64),
+            (10, dynstr.len() as u64),
+            (7, RELA as u64),
+            (8, 2 * 24),
+            (0, 0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            write(
+                &mut image,
+                DYNAMIC + i * 16,
+                &[&tag.to_le_bytes(), &val.to_le_bytes()],
+            );
+        }
+
+        // The section headers: the null section, `.dynsym`, and `.data`.
+        for (i, (type_, addr, size, entsize)) in [
+            (11u32, DYNSYM as u64, 3 * 24, 24u64),
+            (1, DATA as u64, DATA_LEN, 0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            write(
+                &mut image,
+                SHDRS + (i + 1) * 64,
+                &[
+                    &0u32.to_le_bytes(),
+                    &type_.to_le_bytes(),
+                    &0u64.to_le_bytes(),
+                    &addr.to_le_bytes(),
+                    &addr.to_le_bytes(),
+                    &size.to_le_bytes(),
+                    &[0; 16],
+                    &entsize.to_le_bytes(),
+                ],
+            );
+        }
+
+        image
+    }
+
+    fn write(image: &mut [u8], offset: usize, fields: &[&[u8]]) {
+        let mut offset = offset;
+        for field in fields {
+            image[offset..offset + field.len()].copy_from_slice(field);
+            offset += field.len();
+        }
+    }
+}
@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The table of kernel symbols that are exported to dynamic components.
+
+/// A kernel symbol that can be referenced by dynamic components.
+///
+/// Use [`export_symbol!`] to put a symbol into the table.
+///
+/// [`export_symbol!`]: crate::export_symbol
+#[derive(Debug)]
+#[repr(C)]
+pub struct ExportedSymbol {
+    name: &'static str,
+    addr: *const (),
+    controlled: bool,
+}
+
+// SAFETY: The address is only used as an integer to be filled into the
+// dynamic components.
+unsafe impl Sync for ExportedSymbol {}
+
+impl ExportedSymbol {
+    /// Creates an exported symbol.
+    ///
+    /// A controlled symbol is only resolved for the dynamic components that
+    /// are explicitly allowed to access it. See
+    /// [`super::LoadOptions::allow_controlled`].
+    #[doc(hidden)]
+    pub const fn new(name: &'static str, addr: *const (), controlled: bool) -> Self {
+        Self {
+            name,
+            addr,
+            controlled,
+        }
+    }
+
+    /// Returns the name of the symbol.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the address of the symbol.
+    pub fn addr(&self) -> usize {
+        self.addr as usize
+    }
+
+    /// Returns whether the access to the symbol is controlled.
+    pub fn is_controlled(&self) -> bool {
+        self.controlled
+    }
+}
+
+/// Exports a kernel function or static variable to dynamic components.
+///
+/// The symbol is exported with its identifier as the name. Controlled symbols
+/// are only resolved for the dynamic components that are explicitly allowed to
+/// access them, which mirrors `#[controlled]` items of the component system.
+///
+/// # Examples
+///
+/// ```ignore
+/// pub fn foo() {}
+/// pub static BAR: AtomicUsize = AtomicUsize::new(0);
+///
+/// ostd::export_symbol!(foo);
+/// ostd::export_symbol!(controlled static BAR);
+/// ```
+#[macro_export]
+macro_rules! export_symbol {
+    ($name:ident) => {
+        $crate::export_symbol!(@export $name as *const (), $name, false);
+    };
+    (static $name:ident) => {
+        $crate::export_symbol!(@export core::ptr::addr_of!($name) as *const (), $name, false);
+    };
+    (controlled $name:ident) => {
+        $crate::export_symbol!(@export $name as *const (), $name, true);
+    };
+    (controlled static $name:ident) => {
+        $crate::export_symbol!(@export core::ptr::addr_of!($name) as *const (), $name, true);
+    };
+    (@export $addr:expr, $name:ident, $controlled:expr) => {
+        const _: () = {
+            #[used]
+            #[link_section = ".ksymtab"]
+            static EXPORTED_SYMBOL: $crate::dyn_comp::ExportedSymbol =
+                $crate::dyn_comp::ExportedSymbol::new(stringify!($name), $addr, $controlled);
+        };
+    };
+}
+
+/// Returns all the exported kernel symbols.
+pub fn exported_symbols() -> &'static [ExportedSymbol] {
+    extern "C" {
+        fn __ksymtab_start();
+        fn __ksymtab_end();
+    }
+
+    let start = __ksymtab_start as usize;
+    let len = (__ksymtab_end as usize - start) / size_of::<ExportedSymbol>();
+    // SAFETY: The linker script places all the `ExportedSymbol`s created by
+    // `export_symbol!` contiguously between the two symbols.
+    unsafe { core::slice::from_raw_parts(start as *const ExportedSymbol, len) }
+}
+
+/// Finds an exported kernel symbol by its name.
+pub(super) fn find_exported_symbol(name: &str) -> Option<&'static ExportedSymbol> {
+    exported_symbols().iter().find(|symbol| symbol.name == name)
+}
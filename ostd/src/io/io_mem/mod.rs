@@ -3,6 +3,7 @@
 //! I/O memory and its allocator that allocates memory I/O (MMIO) to device drivers.
 
 mod allocator;
+mod register;
 
 use core::ops::{Deref, Range};
 
@@ -10,6 +11,9 @@ use align_ext::AlignExt;
 
 pub(super) use self::allocator::init;
 pub(crate) use self::allocator::IoMemAllocatorBuilder;
+pub use self::register::{
+    ReadOnly, ReadWrite, Readable, Register, RegisterAccess, Writable, WriteOnly,
+};
 use crate::{
     mm::{
         kspace::kvirt_area::{KVirtArea, Untracked},
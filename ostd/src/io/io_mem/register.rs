@@ -0,0 +1,238 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Typed registers in I/O memory.
+
+use core::{
+    marker::PhantomData,
+    sync::atomic::{fence, Ordering},
+};
+
+use super::IoMem;
+use crate::mm::{HasPaddr, PodOnce, VmIoOnce};
+
+/// The access permission of a [`Register`].
+pub trait RegisterAccess: private::Sealed {}
+
+/// A [`RegisterAccess`] that allows reading.
+pub trait Readable: RegisterAccess {}
+
+/// A [`RegisterAccess`] that allows writing.
+pub trait Writable: RegisterAccess {}
+
+/// The access permission of read-only registers.
+#[derive(Debug)]
+pub enum ReadOnly {}
+
+/// The access permission of write-only registers.
+#[derive(Debug)]
+pub enum WriteOnly {}
+
+/// The access permission of registers that can be both read and written.
+#[derive(Debug)]
+pub enum ReadWrite {}
+
+impl RegisterAccess for ReadOnly {}
+impl RegisterAccess for WriteOnly {}
+impl RegisterAccess for ReadWrite {}
+impl Readable for ReadOnly {}
+impl Readable for ReadWrite {}
+impl Writable for WriteOnly {}
+impl Writable for ReadWrite {}
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for super::ReadOnly {}
+    impl Sealed for super::WriteOnly {}
+    impl Sealed for super::ReadWrite {}
+}
+
+/// A typed register in I/O memory.
+///
+/// A register of type `T` is accessed with a single non-tearing load or store of the size of `T`.
+/// The access permission `A` is one of [`ReadOnly`], [`WriteOnly`], and [`ReadWrite`].
+///
+/// # Memory ordering
+///
+/// Like `readl` and `writel` in Linux, [`Self::read`] and [`Self::write`] are ordered with respect
+/// to normal memory accesses:
+///  - Memory accesses after [`Self::read`] will not be performed before the register is read.
+///    For example, after reading a status register, the DMA buffers it refers to can be read.
+///  - Memory accesses before [`Self::write`] will be performed before the register is written.
+///    For example, after filling the DMA buffers, a doorbell register can be written to notify
+///    the device.
+///
+/// [`Self::read_relaxed`] and [`Self::write_relaxed`] do not provide these guarantees. They are
+/// only ordered with respect to other accesses to the same I/O memory.
+#[derive(Debug)]
+pub struct Register<'a, T, A> {
+    io_mem: &'a IoMem,
+    offset: usize,
+    phantom: PhantomData<(T, A)>,
+}
+
+impl<'a, T: PodOnce, A: RegisterAccess> Register<'a, T, A> {
+    /// Creates a register at `offset` of the I/O memory.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the register is out of the bounds of the I/O memory or is not
+    /// aligned to the size of `T`.
+    pub fn new(io_mem: &'a IoMem, offset: usize) -> Self {
+        assert!(
+            offset
+                .checked_add(size_of::<T>())
+                .is_some_and(|end| end <= io_mem.length()),
+            "the register is out of bounds"
+        );
+        assert!(
+            (io_mem.paddr() + offset) % align_of::<T>() == 0,
+            "the register is not aligned"
+        );
+
+        Self {
+            io_mem,
+            offset,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the offset of the register in the I/O memory.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<T: PodOnce, A: Readable> Register<'_, T, A> {
+    /// Reads the register.
+    ///
+    /// See [the type-level documentation](Self#memory-ordering) for the memory ordering.
+    pub fn read(&self) -> T {
+        let value = self.read_relaxed();
+        fence(Ordering::Acquire);
+        value
+    }
+
+    /// Reads the register without ordering it with respect to normal memory accesses.
+    pub fn read_relaxed(&self) -> T {
+        // The bounds and the alignment have been checked in `Self::new`.
+        self.io_mem.read_once(self.offset).unwrap()
+    }
+}
+
+impl<T: PodOnce, A: Writable> Register<'_, T, A> {
+    /// Writes the register.
+    ///
+    /// See [the type-level documentation](Self#memory-ordering) for the memory ordering.
+    pub fn write(&self, value: T) {
+        fence(Ordering::Release);
+        self.write_relaxed(value);
+    }
+
+    /// Writes the register without ordering it with respect to normal memory accesses.
+    pub fn write_relaxed(&self, value: T) {
+        // The bounds and the alignment have been checked in `Self::new`.
+        self.io_mem.write_once(self.offset, &value).unwrap();
+    }
+}
+
+impl<T: PodOnce, A: Readable + Writable> Register<'_, T, A> {
+    /// Reads the register, modifies the value with `f`, and writes the result back.
+    ///
+    /// Note that the read-modify-write sequence is _not_ atomic. The caller must ensure that no
+    /// one else modifies the register concurrently.
+    pub fn modify<F>(&self, f: F)
+    where
+        F: FnOnce(T) -> T,
+    {
+        let value = self.read();
+        self.write(f(value));
+    }
+}
+
+/// Defines a block of typed registers in I/O memory.
+///
+/// Each register is declared as `name: Access<Type> @ offset`, where `Access` is one of
+/// [`ReadOnly`], [`WriteOnly`], and [`ReadWrite`]. The macro generates a struct that owns the
+/// [`IoMem`], with a constructor `new(io_mem)` and an accessor method per register that returns
+/// the corresponding [`Register`]. Only the registers that the driver uses need to be declared.
+///
+/// The access permission of each register is checked at compile time, so writing to a read-only
+/// register or reading from a write-only register does not compile. The bounds and the alignment
+/// of each register are checked when the register block is created, so the accesses afterwards
+/// cannot fail.
+///
+/// # Panics
+///
+/// The generated `new` method will panic if some registers are out of the bounds of the I/O
+/// memory or are misaligned.
+///
+/// # Examples
+///
+/// ```rust
+/// use ostd::{io::IoMem, io_registers};
+///
+/// io_registers! {
+///     /// The registers of the Goldfish RTC.
+///     pub struct GoldfishRtcRegisters {
+///         /// The lower 32 bits of the current time in nanoseconds.
+///         pub time_low: ReadOnly<u32> @ 0x00,
+///         /// The upper 32 bits of the current time in nanoseconds.
+///         pub time_high: ReadOnly<u32> @ 0x04,
+///         /// Clears the pending alarm interrupt.
+///         pub clear_interrupt: WriteOnly<u32> @ 0x1c,
+///     }
+/// }
+///
+/// fn read_time(regs: &GoldfishRtcRegisters) -> u64 {
+///     let low = regs.time_low().read();
+///     let high = regs.time_high().read();
+///     ((high as u64) << 32) | low as u64
+/// }
+/// ```
+///
+/// [`ReadOnly`]: crate::io::ReadOnly
+/// [`WriteOnly`]: crate::io::WriteOnly
+/// [`ReadWrite`]: crate::io::ReadWrite
+/// [`IoMem`]: crate::io::IoMem
+/// [`Register`]: crate::io::Register
+#[macro_export]
+macro_rules! io_registers {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$reg_attr:meta])*
+                $reg_vis:vis $reg:ident: $access:ident<$ty:ty> @ $offset:expr
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Debug)]
+        $vis struct $name {
+            io_mem: $crate::io::IoMem,
+        }
+
+        impl $name {
+            /// Creates the register block on the I/O memory.
+            ///
+            /// # Panics
+            ///
+            /// This method will panic if some registers are out of the bounds of the I/O memory or
+            /// are misaligned.
+            $vis fn new(io_mem: $crate::io::IoMem) -> Self {
+                $(
+                    $crate::io::Register::<$ty, $crate::io::$access>::new(&io_mem, $offset);
+                )*
+                Self { io_mem }
+            }
+
+            $(
+                $(#[$reg_attr])*
+                $reg_vis fn $reg(&self) -> $crate::io::Register<'_, $ty, $crate::io::$access> {
+                    $crate::io::Register::new(&self.io_mem, $offset)
+                }
+            )*
+        }
+    };
+}
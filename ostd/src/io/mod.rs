@@ -6,13 +6,20 @@
 //! through _allocators_. There are two types of device I/O:
 //!  - `IoMem` for memory I/O (MMIO).
 //!  - `IoPort` for port I/O (PIO).
+//!
+//! The registers in memory I/O can be accessed in a typed manner via [`Register`]s, which are
+//! usually declared by the [`io_registers!`] macro.
+//!
+//! [`io_registers!`]: crate::io_registers
 
 mod io_mem;
 
 use cfg_if::cfg_if;
 
-pub use self::io_mem::IoMem;
 pub(crate) use self::io_mem::IoMemAllocatorBuilder;
+pub use self::io_mem::{
+    IoMem, ReadOnly, ReadWrite, Readable, Register, RegisterAccess, Writable, WriteOnly,
+};
 
 cfg_if!(
     if #[cfg(target_arch = "x86_64")] {
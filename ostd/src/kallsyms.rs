@@ -0,0 +1,378 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The symbol table of the kernel.
+//!
+//! The table lists the names, the addresses and the sizes of the symbols in
+//! the kernel image. It is embedded into the reserved `.kallsyms` section by
+//! OSDK after the kernel is linked, so no symbols are available if the kernel
+//! is not built with OSDK. The table is used to symbolize the addresses in the
+//! stack traces and is exposed to the user space as `/proc/kallsyms`.
+//!
+//! The table is laid out as follows, with all the integers in little endian:
+//!
+//! ```text
+//! +--------+-------------+-----------+-------------------+-----------------+
+//! | header | addresses[] | sizes[]   | markers[]         | names           |
+//! |        | (u64 each)  | (u32 each)| (u32 each, one    | (front-coded)   |
+//! |        |             |           |  per 16 symbols)  |                 |
+//! +--------+-------------+-----------+-------------------+-----------------+
+//! ```
+//!
+//! The symbols are sorted by their addresses. Each name is a record of
+//! `[type, prefix_len, suffix_len, suffix...]`, where the name shares its
+//! first `prefix_len` bytes with the name of the previous symbol. The prefix
+//! is reset to be empty at every 16 symbols, whose offsets in the names are
+//! recorded as the markers, so that a name can be decoded without decoding
+//! all the names before it.
+
+use core::{fmt, ops::Range};
+
+use crate::mm::Vaddr;
+
+/// The capacity of the reserved `.kallsyms` section.
+///
+/// OSDK fails to build the kernel if its symbol table exceeds the capacity.
+const KALLSYMS_CAPACITY: usize = 4 << 20;
+
+/// The magic number of the table, i.e., `b"KSYM"`.
+const MAGIC: u32 = u32::from_le_bytes(*b"KSYM");
+
+/// The size of the header, which is `[magic, nr_symbols, names_len, reserved]`.
+const HEADER_SIZE: usize = 16;
+
+/// The number of symbols whose names are coded in a group.
+const SYMBOLS_PER_MARKER: usize = 16;
+
+/// The maximum length of a symbol name in bytes.
+pub const SYMBOL_NAME_MAX: usize = u8::MAX as usize;
+
+/// The storage of the table, which is filled by OSDK in the kernel image.
+///
+/// The initial value must be non-zero to prevent the section from being
+/// placed in `.bss`-like segments that occupy no space in the image.
+#[used]
+#[link_section = ".kallsyms"]
+static KALLSYMS_STORAGE: [u8; KALLSYMS_CAPACITY] = {
+    let mut storage = [0; KALLSYMS_CAPACITY];
+    storage[KALLSYMS_CAPACITY - 1] = 0xff;
+    storage
+};
+
+/// A symbol in the kernel.
+#[derive(Clone)]
+pub struct KernelSymbol {
+    addr: Vaddr,
+    size: usize,
+    type_: u8,
+    name: [u8; SYMBOL_NAME_MAX],
+    name_len: usize,
+}
+
+impl KernelSymbol {
+    /// Returns the address of the symbol.
+    pub fn addr(&self) -> Vaddr {
+        self.addr
+    }
+
+    /// Returns the size of the symbol in bytes.
+    ///
+    /// The size is zero if it is unknown, e.g., for the symbols defined in
+    /// the assembly code or the linker script.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the type of the symbol, in the form of `nm`.
+    ///
+    /// For example, `T` or `t` for text symbols, `R` or `r` for read-only
+    /// data symbols, `D` or `d` for data symbols, and `B` or `b` for `.bss`
+    /// symbols. The lowercase letters denote local symbols.
+    pub fn type_(&self) -> char {
+        self.type_ as char
+    }
+
+    /// Returns the demangled name of the symbol.
+    pub fn name(&self) -> &str {
+        let name = &self.name[..self.name_len];
+        // Long names may be truncated in the middle of a UTF-8 sequence.
+        match core::str::from_utf8(name) {
+            Ok(name) => name,
+            // SAFETY: The bytes before the error are valid UTF-8.
+            Err(err) => unsafe { core::str::from_utf8_unchecked(&name[..err.valid_up_to()]) },
+        }
+    }
+}
+
+impl fmt::Debug for KernelSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KernelSymbol")
+            .field("addr", &self.addr)
+            .field("size", &self.size)
+            .field("type", &self.type_())
+            .field("name", &self.name())
+            .finish()
+    }
+}
+
+/// Returns whether the symbol table is embedded in the kernel.
+pub fn is_available() -> bool {
+    Table::get().is_some()
+}
+
+/// Returns the number of symbols in the kernel.
+pub fn nr_symbols() -> usize {
+    Table::get().map_or(0, |table| table.nr_symbols)
+}
+
+/// Looks up the symbol that contains the address.
+///
+/// On success, this function returns the symbol and the offset of the address
+/// from the start of the symbol. If the size of the symbol is unknown, the
+/// address is regarded as contained in the symbol preceding it.
+pub fn lookup(addr: Vaddr) -> Option<(KernelSymbol, usize)> {
+    let table = Table::get()?;
+
+    // The index of the last symbol whose address is not greater than `addr`.
+    let index = table
+        .partition_point(|i| table.addr(i) <= addr)
+        .checked_sub(1)?;
+    let symbol = table.symbol(index);
+    let offset = addr - symbol.addr;
+    if symbol.size != 0 && offset >= symbol.size {
+        return None;
+    }
+    Some((symbol, offset))
+}
+
+/// Looks up the symbol by its name.
+pub fn lookup_by_name(name: &str) -> Option<KernelSymbol> {
+    iter().find(|symbol| symbol.name() == name)
+}
+
+/// Returns an iterator over all the symbols, sorted by their addresses.
+pub fn iter() -> impl Iterator<Item = KernelSymbol> {
+    iter_from(0)
+}
+
+/// Returns an iterator over the symbols from the `start`-th one, sorted by
+/// their addresses.
+///
+/// The names are decoded from the marker preceding the `start`-th symbol, so
+/// this is much faster than skipping the symbols returned by [`iter`].
+pub fn iter_from(start: usize) -> impl Iterator<Item = KernelSymbol> {
+    let table = Table::get();
+    let nr_symbols = table.as_ref().map_or(0, |table| table.nr_symbols);
+    let start = start.min(nr_symbols);
+    let mut decoder = NameDecoder::new();
+    (start - start % SYMBOLS_PER_MARKER..nr_symbols)
+        .map(move |i| {
+            let table = table.as_ref().unwrap();
+            if i % SYMBOLS_PER_MARKER == 0 {
+                decoder.seek(table.marker(i / SYMBOLS_PER_MARKER));
+            }
+            decoder.decode_next(table);
+            table.new_symbol(i, &decoder)
+        })
+        .skip(start % SYMBOLS_PER_MARKER)
+}
+
+/// Formats the address with the symbol that contains it, e.g.,
+/// `ostd::task::Task::run+0x2a/0x80`.
+pub struct Symbolized(pub Vaddr);
+
+impl fmt::Display for Symbolized {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match lookup(self.0) {
+            Some((symbol, offset)) if symbol.size != 0 => {
+                write!(f, "{}+{:#x}/{:#x}", symbol.name(), offset, symbol.size)
+            }
+            Some((symbol, offset)) => write!(f, "{}+{:#x}", symbol.name(), offset),
+            None => write!(f, "{:#x}", self.0),
+        }
+    }
+}
+
+/// The parsed view of the embedded table.
+struct Table {
+    bytes: &'static [u8],
+    nr_symbols: usize,
+    addrs: Range<usize>,
+    sizes: Range<usize>,
+    markers: Range<usize>,
+    names: Range<usize>,
+}
+
+impl Table {
+    fn get() -> Option<Self> {
+        extern "C" {
+            fn __kallsyms_start();
+            fn __kallsyms_end();
+        }
+
+        let start = __kallsyms_start as usize;
+        let len = __kallsyms_end as usize - start;
+        // SAFETY: The section is reserved by the linker script and is never
+        // written after the kernel starts. The bytes are read through the
+        // linker symbols, so that the compiler cannot assume them to have
+        // the initial value of `KALLSYMS_STORAGE`.
+        let bytes = unsafe { core::slice::from_raw_parts(start as *const u8, len) };
+
+        if bytes.len() < HEADER_SIZE || read_u32(bytes, 0) != MAGIC {
+            return None;
+        }
+        let nr_symbols = read_u32(bytes, 4) as usize;
+        let names_len = read_u32(bytes, 8) as usize;
+
+        let addrs = HEADER_SIZE..HEADER_SIZE + nr_symbols * size_of::<u64>();
+        let sizes = addrs.end..addrs.end + nr_symbols * size_of::<u32>();
+        let nr_markers = nr_symbols.div_ceil(SYMBOLS_PER_MARKER);
+        let markers = sizes.end..sizes.end + nr_markers * size_of::<u32>();
+        let names = markers.end..markers.end + names_len;
+        if names.end > bytes.len() {
+            return None;
+        }
+
+        Some(Self {
+            bytes,
+            nr_symbols,
+            addrs,
+            sizes,
+            markers,
+            names,
+        })
+    }
+
+    fn addr(&self, index: usize) -> Vaddr {
+        let offset = self.addrs.start + index * size_of::<u64>();
+        u64::from_le_bytes(self.bytes[offset..offset + 8].try_into().unwrap()) as Vaddr
+    }
+
+    fn size(&self, index: usize) -> usize {
+        read_u32(self.bytes, self.sizes.start + index * size_of::<u32>()) as usize
+    }
+
+    fn marker(&self, index: usize) -> usize {
+        read_u32(self.bytes, self.markers.start + index * size_of::<u32>()) as usize
+    }
+
+    fn names(&self) -> &'static [u8] {
+        &self.bytes[self.names.clone()]
+    }
+
+    /// Returns the number of symbols that satisfy the predicate, which must
+    /// hold for a prefix of the symbols.
+    fn partition_point(&self, pred: impl Fn(usize) -> bool) -> usize {
+        let (mut lo, mut hi) = (0, self.nr_symbols);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if pred(mid) {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+
+    fn symbol(&self, index: usize) -> KernelSymbol {
+        let mut decoder = NameDecoder::new();
+        decoder.seek(self.marker(index / SYMBOLS_PER_MARKER));
+        for _ in 0..=index % SYMBOLS_PER_MARKER {
+            decoder.decode_next(self);
+        }
+        self.new_symbol(index, &decoder)
+    }
+
+    fn new_symbol(&self, index: usize, decoder: &NameDecoder) -> KernelSymbol {
+        KernelSymbol {
+            addr: self.addr(index),
+            size: self.size(index),
+            type_: decoder.type_,
+            name: decoder.name,
+            name_len: decoder.name_len,
+        }
+    }
+}
+
+/// The decoder of the front-coded names.
+struct NameDecoder {
+    /// The offset of the next record in the names.
+    pos: usize,
+    type_: u8,
+    name: [u8; SYMBOL_NAME_MAX],
+    name_len: usize,
+}
+
+impl NameDecoder {
+    fn new() -> Self {
+        Self {
+            pos: 0,
+            type_: b'?',
+            name: [0; SYMBOL_NAME_MAX],
+            name_len: 0,
+        }
+    }
+
+    fn seek(&mut self, pos: usize) {
+        self.pos = pos;
+        self.name_len = 0;
+    }
+
+    /// Decodes the next record. Malformed records yield truncated names.
+    fn decode_next(&mut self, table: &Table) {
+        let names = table.names();
+        let Some(&[type_, prefix_len, suffix_len]) = names.get(self.pos..self.pos + 3) else {
+            self.name_len = 0;
+            return;
+        };
+        let suffix_start = self.pos + 3;
+        let suffix_end = (suffix_start + suffix_len as usize).min(names.len());
+        let suffix = &names[suffix_start..suffix_end];
+        let prefix_len = (prefix_len as usize).min(self.name_len);
+        let name_len = (prefix_len + suffix.len()).min(SYMBOL_NAME_MAX);
+
+        self.name[prefix_len..name_len].copy_from_slice(&suffix[..name_len - prefix_len]);
+        self.name_len = name_len;
+        self.type_ = type_;
+        self.pos = suffix_end;
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    #[ktest]
+    fn lookup_kernel_symbols() {
+        // The table is not embedded if `nm` is not available on the host.
+        if !is_available() {
+            return;
+        }
+
+        let addr = lookup_kernel_symbols as usize;
+        let (symbol, offset) = lookup(addr).unwrap();
+        assert_eq!(offset, 0);
+        assert_eq!(symbol.addr(), addr);
+        assert!(symbol.name().ends_with("lookup_kernel_symbols"));
+        assert_eq!(symbol.type_().to_ascii_lowercase(), 't');
+
+        let found = lookup_by_name(symbol.name()).unwrap();
+        assert_eq!(found.addr(), addr);
+
+        assert_eq!(iter().count(), nr_symbols());
+        assert!(iter().is_sorted_by_key(|symbol| symbol.addr()));
+
+        for start in [1, SYMBOLS_PER_MARKER, SYMBOLS_PER_MARKER + 3] {
+            let expected = iter().nth(start).unwrap();
+            let found = iter_from(start).next().unwrap();
+            assert_eq!(found.addr(), expected.addr());
+            assert_eq!(found.name(), expected.name());
+        }
+        assert_eq!(iter_from(nr_symbols()).count(), 0);
+    }
+}
@@ -34,12 +34,17 @@ pub mod arch;
 pub mod boot;
 pub mod bus;
 pub mod console;
+pub mod coverage;
 pub mod cpu;
+#[cfg(feature = "dyn_comp")]
+pub mod dyn_comp;
 mod error;
 pub mod io;
+pub mod kallsyms;
 pub mod logger;
 pub mod mm;
 pub mod panic;
+pub mod power;
 pub mod prelude;
 pub mod smp;
 pub mod sync;
@@ -124,6 +129,9 @@ unsafe fn init() {
         mm::kspace::activate_kernel_page_table();
     }
 
+    #[cfg(target_arch = "x86_64")]
+    arch::gdbstub::init();
+
     bus::init();
 
     arch::irq::enable_local();
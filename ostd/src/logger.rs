@@ -7,12 +7,18 @@
 //!
 //! Generally IRQs are disabled while printing. So do not print long log messages.
 
-use core::str::FromStr;
+use core::{
+    fmt::{self, Write},
+    str::FromStr,
+};
 
 use log::{LevelFilter, Metadata, Record};
 use spin::Once;
 
-use crate::boot::EARLY_INFO;
+use crate::{
+    boot::EARLY_INFO,
+    sync::{LocalIrqDisabled, SpinLock},
+};
 
 static LOGGER: Logger = Logger::new();
 
@@ -51,6 +57,13 @@ impl log::Log for Logger {
     }
 
     fn log(&self, record: &Record) {
+        // The message is not kept if the buffer is busy, i.e., being updated
+        // by another CPU or by a message whose formatting panics. Losing the
+        // message is preferred to deadlocking in the panic handler.
+        if let Some(mut logs) = RECENT_LOGS.try_lock() {
+            logs.push(record);
+        }
+
         if let Some(logger) = self.backend.get() {
             return logger.log(record);
         };
@@ -67,6 +80,101 @@ impl log::Log for Logger {
     }
 }
 
+/// The number of the recent log messages that are kept.
+const NR_RECENT_LOGS: usize = 32;
+
+/// The maximum length of a kept log message in bytes.
+///
+/// Longer messages are truncated.
+const RECENT_LOG_MAX_LEN: usize = 160;
+
+/// The recent log messages, which are included in the panic reports.
+static RECENT_LOGS: SpinLock<RecentLogs, LocalIrqDisabled> = SpinLock::new(RecentLogs::new());
+
+/// A ring buffer of the recent log messages.
+///
+/// The messages are stored in fixed-size slots so that logging never
+/// allocates memory.
+struct RecentLogs {
+    slots: [[u8; RECENT_LOG_MAX_LEN]; NR_RECENT_LOGS],
+    lens: [usize; NR_RECENT_LOGS],
+    /// The index of the slot for the next message.
+    next: usize,
+    /// The number of the messages in the buffer.
+    len: usize,
+}
+
+impl RecentLogs {
+    const fn new() -> Self {
+        Self {
+            slots: [[0; RECENT_LOG_MAX_LEN]; NR_RECENT_LOGS],
+            lens: [0; NR_RECENT_LOGS],
+            next: 0,
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, record: &Record) {
+        let mut writer = SlotWriter {
+            slot: &mut self.slots[self.next],
+            len: 0,
+        };
+        let _ = write!(writer, "{}: {}", record.level(), record.args());
+
+        self.lens[self.next] = writer.len;
+        self.next = (self.next + 1) % NR_RECENT_LOGS;
+        self.len = (self.len + 1).min(NR_RECENT_LOGS);
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &str> {
+        let first = (self.next + NR_RECENT_LOGS - self.len) % NR_RECENT_LOGS;
+        (0..self.len).map(move |i| {
+            let index = (first + i) % NR_RECENT_LOGS;
+            // SAFETY: The slot is filled by `SlotWriter`, which only keeps
+            // whole UTF-8 characters.
+            unsafe { core::str::from_utf8_unchecked(&self.slots[index][..self.lens[index]]) }
+        })
+    }
+}
+
+/// A writer that fills a slot and truncates the excessive output.
+struct SlotWriter<'a> {
+    slot: &'a mut [u8; RECENT_LOG_MAX_LEN],
+    len: usize,
+}
+
+impl Write for SlotWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let avail = RECENT_LOG_MAX_LEN - self.len;
+        let mut end = s.len().min(avail);
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        self.slot[self.len..self.len + end].copy_from_slice(&s.as_bytes()[..end]);
+        self.len += end;
+        if end < s.len() {
+            // Stop formatting since the slot is full.
+            return Err(fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+/// Calls the function on each of the recent log messages, from the oldest to
+/// the newest.
+///
+/// Nothing is done if the messages are being recorded, e.g., if the caller
+/// panics while logging.
+pub(crate) fn for_each_recent_log(mut f: impl FnMut(&str)) {
+    let Some(logs) = RECENT_LOGS.try_lock() else {
+        return;
+    };
+    for log in logs.iter() {
+        f(log);
+    }
+}
+
 /// Initialize the logger. Users should avoid using the log macros before this function is called.
 pub(crate) fn init() {
     let level = get_log_level().unwrap_or(LevelFilter::Off);
@@ -173,6 +173,23 @@ pub trait GlobalFrameAllocator: Sync {
     ///
     /// The added memory can be uninitialized.
     fn add_free_memory(&self, addr: Paddr, size: usize);
+
+    /// Removes a contiguous range of free frames from the allocator.
+    ///
+    /// The caller guarantees that `addr` and `size` are both aligned to
+    /// [`PAGE_SIZE`]. This is used to take back the memory to be
+    /// hot-unplugged.
+    ///
+    /// If all of the frames in the range are free in the allocator, they are
+    /// removed and will never be allocated until added again by
+    /// [`GlobalFrameAllocator::add_free_memory`]. Then the method returns
+    /// `true`. Otherwise, the allocator should be left untouched and the
+    /// method returns `false`.
+    ///
+    /// The default implementation does not support removing memory.
+    fn remove_free_memory(&self, _addr: Paddr, _size: usize) -> bool {
+        false
+    }
 }
 
 extern "Rust" {
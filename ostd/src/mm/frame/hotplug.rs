@@ -0,0 +1,150 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Memory hot-plugging.
+//!
+//! Memory devices such as ACPI memory devices and virtio-mem can make more
+//! physical memory available to the guest after boot, or take some of the
+//! memory back.
+//!
+//! The physical memory above the memory known at boot time is managed in the
+//! unit of sections of [`SECTION_SIZE`]. A section is brought online when any
+//! memory in it is added for the first time. Onlining a section sets up the
+//! frame metadata and the linear mapping for the whole section. Then the
+//! hot-plugged memory can be added to the global frame allocator with
+//! [`add_memory`] and removed from it with [`remove_memory`].
+//!
+//! Currently, an online section stays online even if all the memory in it has
+//! been removed. The metadata of a section costs less than 2% of its size.
+
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use align_ext::AlignExt;
+
+use super::{
+    allocator::get_global_frame_allocator,
+    meta::{alloc_hotplug_meta_pages, get_slot, REF_COUNT_UNUSED},
+};
+use crate::{
+    error::Error,
+    mm::{kspace::map_hotplugged_memory, Paddr, PAGE_SIZE},
+    prelude::*,
+    sync::SpinLock,
+};
+
+/// The size of a memory section.
+pub const SECTION_SIZE: usize = 128 * 1024 * 1024;
+
+/// The maximum physical address that can be hot-plugged.
+const MAX_HOTPLUG_PADDR: Paddr = 1 << 46;
+
+const NR_SECTIONS: usize = MAX_HOTPLUG_PADDR / SECTION_SIZE;
+
+/// The bitmap of hot-plugged sections that have been brought online.
+///
+/// The sections covered by the memory known at boot time are not in the bitmap.
+static ONLINE_SECTIONS: [AtomicU64; NR_SECTIONS / 64] =
+    [const { AtomicU64::new(0) }; NR_SECTIONS / 64];
+
+/// The lock to serialize hot-plugging operations.
+static HOTPLUG_LOCK: SpinLock<()> = SpinLock::new(());
+
+/// Returns whether the hot-plugged section containing the address is online.
+pub(super) fn is_section_online(paddr: Paddr) -> bool {
+    let index = paddr / SECTION_SIZE;
+    if index >= NR_SECTIONS {
+        return false;
+    }
+    ONLINE_SECTIONS[index / 64].load(Ordering::Acquire) & (1 << (index % 64)) != 0
+}
+
+/// Brings the hot-plugged section online.
+fn online_section(index: usize) -> Result<()> {
+    let range = index * SECTION_SIZE..(index + 1) * SECTION_SIZE;
+    let meta_pages = alloc_hotplug_meta_pages(SECTION_SIZE / PAGE_SIZE)?;
+    map_hotplugged_memory(&range, meta_pages)?;
+
+    log::info!("Memory section {:#x?} is online", range);
+    ONLINE_SECTIONS[index / 64].fetch_or(1 << (index % 64), Ordering::Release);
+
+    Ok(())
+}
+
+/// Adds a range of hot-plugged physical memory to the global frame allocator.
+///
+/// The range must be page-aligned and must not overlap with any memory that
+/// is in use or has been added to the allocator before. If the range is above
+/// the memory known at boot time, it must not share a section with the memory
+/// known at boot time.
+pub fn add_memory(range: Range<Paddr>) -> Result<()> {
+    check_range(&range)?;
+
+    let _guard = HOTPLUG_LOCK.lock();
+
+    let boot_end = super::max_paddr();
+    if range.end > boot_end {
+        if range.start < boot_end.align_up(SECTION_SIZE) {
+            return Err(Error::InvalidArgs);
+        }
+
+        let sections = range.start / SECTION_SIZE..range.end.div_ceil(SECTION_SIZE);
+        for index in sections {
+            if !is_section_online(index * SECTION_SIZE) {
+                online_section(index)?;
+            }
+        }
+    }
+
+    for paddr in range.clone().step_by(PAGE_SIZE) {
+        let slot = get_slot(paddr).map_err(|_| Error::InvalidArgs)?;
+        if slot.ref_count.load(Ordering::Relaxed) != REF_COUNT_UNUSED {
+            return Err(Error::AccessDenied);
+        }
+    }
+
+    log::info!("Adding hot-plugged frames to the allocator: {:x?}", range);
+    get_global_frame_allocator().add_free_memory(range.start, range.len());
+
+    Ok(())
+}
+
+/// Removes a range of physical memory from the global frame allocator so that
+/// it can be unplugged.
+///
+/// All the frames in the range must be free in the allocator. If some of the
+/// frames are in use (or cached by the allocator), this function returns
+/// [`Error::NotEnoughResources`] and the range is left untouched. Such memory
+/// may be removed later after its users release it.
+pub fn remove_memory(range: Range<Paddr>) -> Result<()> {
+    check_range(&range)?;
+
+    let _guard = HOTPLUG_LOCK.lock();
+
+    if range
+        .clone()
+        .step_by(PAGE_SIZE)
+        .any(|paddr| !super::is_tracked(paddr))
+    {
+        return Err(Error::InvalidArgs);
+    }
+
+    if !get_global_frame_allocator().remove_free_memory(range.start, range.len()) {
+        return Err(Error::NotEnoughResources);
+    }
+    log::info!("Removed frames from the allocator: {:x?}", range);
+
+    Ok(())
+}
+
+fn check_range(range: &Range<Paddr>) -> Result<()> {
+    if range.is_empty()
+        || range.start % PAGE_SIZE != 0
+        || range.end % PAGE_SIZE != 0
+        || range.end > MAX_HOTPLUG_PADDR
+    {
+        return Err(Error::InvalidArgs);
+    }
+    Ok(())
+}
@@ -55,8 +55,9 @@ use crate::{
     arch::mm::PagingConsts,
     boot::memory_region::MemoryRegionType,
     const_assert,
+    error::Error,
     mm::{
-        frame::allocator::{self, EarlyAllocatedFrameMeta},
+        frame::allocator::{self, EarlyAllocatedFrameMeta, FrameAllocOptions},
         kspace::LINEAR_MAPPING_BASE_VADDR,
         paddr_to_vaddr, page_size,
         page_table::boot_pt,
@@ -206,7 +207,7 @@ pub(super) fn get_slot(paddr: Paddr) -> Result<&'static MetaSlot, GetFrameError>
     if paddr % PAGE_SIZE != 0 {
         return Err(GetFrameError::NotAligned);
     }
-    if paddr >= super::max_paddr() {
+    if !super::is_tracked(paddr) {
         return Err(GetFrameError::OutOfBound);
     }
 
@@ -530,13 +531,48 @@ fn alloc_meta_frames(tot_nr_frames: usize) -> (usize, Paddr) {
 
     let slots = paddr_to_vaddr(start_paddr) as *mut MetaSlot;
 
-    // Initialize the metadata slots.
-    for i in 0..tot_nr_frames {
-        // SAFETY: The memory is successfully allocated with `tot_nr_frames`
-        // slots so the index must be within the range.
+    // SAFETY: The memory is just allocated with `tot_nr_frames` slots so we
+    // have exclusive access and it's valid for writing.
+    unsafe { init_unused_slots(slots, tot_nr_frames) };
+
+    (nr_meta_pages, start_paddr)
+}
+
+/// Allocates the metadata pages for the frames to be hot-plugged.
+///
+/// All the metadata slots in the returned pages are initialized as unused.
+/// The pages are expected to be mapped at the metadata address of the first
+/// frame later.
+pub(super) fn alloc_hotplug_meta_pages(nr_frames: usize) -> Result<Segment<MetaPageMeta>, Error> {
+    let nr_meta_pages = nr_frames
+        .checked_mul(size_of::<MetaSlot>())
+        .ok_or(Error::Overflow)?
+        .div_ceil(PAGE_SIZE);
+    let meta_pages = FrameAllocOptions::new()
+        .zeroed(false)
+        .alloc_segment_with(nr_meta_pages, |_| MetaPageMeta {})?;
+
+    let slots = paddr_to_vaddr(meta_pages.start_paddr()) as *mut MetaSlot;
+    // SAFETY: The memory is just allocated with at least `nr_frames` slots so
+    // we have exclusive access and it's valid for writing.
+    unsafe { init_unused_slots(slots, nr_frames) };
+
+    Ok(meta_pages)
+}
+
+/// Initializes the metadata slots as unused.
+///
+/// # Safety
+///
+/// The caller must ensure that the memory of `nr` slots starting from
+/// `slots` is exclusively owned and valid for writing.
+unsafe fn init_unused_slots(slots: *mut MetaSlot, nr: usize) {
+    for i in 0..nr {
+        // SAFETY: The index is within the range according to the safety
+        // requirement.
         let slot = unsafe { slots.add(i) };
-        // SAFETY: The memory is just allocated so we have exclusive access and
-        // it's valid for writing.
+        // SAFETY: The memory is exclusively owned and valid for writing
+        // according to the safety requirement.
         unsafe {
             slot.write(MetaSlot {
                 storage: UnsafeCell::new([0; FRAME_METADATA_MAX_SIZE]),
@@ -546,8 +582,6 @@ fn alloc_meta_frames(tot_nr_frames: usize) -> (usize, Paddr) {
             })
         };
     }
-
-    (nr_meta_pages, start_paddr)
 }
 
 /// The metadata of physical pages that cannot be allocated for general use.
@@ -32,6 +32,7 @@
 //! can create custom metadata types by implementing the [`AnyFrameMeta`] trait.
 
 pub mod allocator;
+pub mod hotplug;
 pub mod linked_list;
 pub mod meta;
 pub mod segment;
@@ -60,13 +61,22 @@ use crate::mm::{Paddr, PagingConsts, Vaddr};
 
 static MAX_PADDR: AtomicUsize = AtomicUsize::new(0);
 
-/// Returns the maximum physical address that is tracked by frame metadata.
+/// Returns the maximum physical address that is tracked by frame metadata
+/// since boot.
+///
+/// Frames above this address may also be tracked if they are hot-plugged.
+/// Use [`is_tracked`] to check whether a frame has metadata.
 pub(in crate::mm) fn max_paddr() -> Paddr {
     let max_paddr = MAX_PADDR.load(Ordering::Relaxed) as Paddr;
     debug_assert_ne!(max_paddr, 0);
     max_paddr
 }
 
+/// Returns whether the frame at the physical address is tracked by frame metadata.
+pub(in crate::mm) fn is_tracked(paddr: Paddr) -> bool {
+    paddr < max_paddr() || hotplug::is_section_online(paddr)
+}
+
 /// A smart pointer to a frame.
 ///
 /// A frame is a contiguous range of bytes in physical memory. The [`Frame`]
@@ -315,7 +325,7 @@ impl TryFrom<Frame<dyn AnyFrameMeta>> for UFrame {
 ///  2. The caller must have already held a reference to the frame.
 pub(in crate::mm) unsafe fn inc_frame_ref_count(paddr: Paddr) {
     debug_assert!(paddr % PAGE_SIZE == 0);
-    debug_assert!(paddr < max_paddr());
+    debug_assert!(is_tracked(paddr));
 
     let vaddr: Vaddr = mapping::frame_to_meta::<PagingConsts>(paddr);
     // SAFETY: `vaddr` points to a valid `MetaSlot` that will never be mutably borrowed, so taking
@@ -90,10 +90,10 @@ impl<M: AnyFrameMeta> Segment<M> {
         if range.start % PAGE_SIZE != 0 || range.end % PAGE_SIZE != 0 {
             return Err(GetFrameError::NotAligned);
         }
-        if range.end > super::max_paddr() {
+        assert!(range.start < range.end);
+        if !super::is_tracked(range.start) || !super::is_tracked(range.end - PAGE_SIZE) {
             return Err(GetFrameError::OutOfBound);
         }
-        assert!(range.start < range.end);
         // Construct a segment early to recycle previously forgotten frames if
         // the subsequent operations fails in the middle.
         let mut segment = Self {
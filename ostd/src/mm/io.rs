@@ -47,7 +47,7 @@ use align_ext::AlignExt;
 use inherit_methods_macro::inherit_methods;
 
 use crate::{
-    arch::mm::{__memcpy_fallible, __memset_fallible},
+    arch::mm::{__atomic_cmpxchg_fallible, __memcpy_fallible, __memset_fallible},
     mm::{
         kspace::{KERNEL_BASE_VADDR, KERNEL_END_VADDR},
         MAX_USERSPACE_VADDR,
@@ -886,6 +886,26 @@ impl VmWriter<'_, Fallible> {
             Ok(len_to_set)
         }
     }
+
+    /// Atomically compares and exchanges a `u32` value at the cursor.
+    ///
+    /// The value is replaced with `new_val` if it equals `old_val`. The previous value is
+    /// returned, so the exchange succeeds if and only if the returned value equals `old_val`.
+    /// The cursor is not moved.
+    ///
+    /// If the available space is less than four bytes or the cursor is not aligned, this
+    /// method will return [`Error::InvalidArgs`]. If the memory access fails due to an
+    /// unresolvable page fault, this method will return [`Error::PageFault`].
+    pub fn atomic_compare_exchange(&self, old_val: u32, new_val: u32) -> Result<u32> {
+        if self.avail() < core::mem::size_of::<u32>() || !self.cursor.cast::<u32>().is_aligned() {
+            return Err(Error::InvalidArgs);
+        }
+
+        // SAFETY: The value is aligned and within the memory range specified by the current
+        // writer, so it is either valid for writing or in user space.
+        unsafe { __atomic_cmpxchg_fallible(self.cursor.cast(), old_val, new_val) }
+            .ok_or(Error::PageFault)
+    }
 }
 
 impl<Fallibility> VmWriter<'_, Fallibility> {
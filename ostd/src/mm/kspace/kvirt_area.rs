@@ -113,6 +113,22 @@ impl KVirtArea<Tracked> {
         map_offset: usize,
         pages: impl Iterator<Item = Frame<T>>,
         prop: PageProperty,
+    ) -> Self {
+        Self::map_pages_with_props(area_size, map_offset, pages.map(|page| (page, prop)))
+    }
+
+    /// Create a kernel virtual area and map pages into it, each with its own
+    /// page property.
+    ///
+    /// See [`Self::map_pages`] for the meaning of the arguments.
+    ///
+    /// # Panics
+    ///
+    /// This function panics in the same cases as [`Self::map_pages`].
+    pub fn map_pages_with_props<T: AnyFrameMeta>(
+        area_size: usize,
+        map_offset: usize,
+        pages: impl Iterator<Item = (Frame<T>, PageProperty)>,
     ) -> Self {
         assert!(area_size % PAGE_SIZE == 0);
         assert!(map_offset % PAGE_SIZE == 0);
@@ -123,7 +139,7 @@ impl KVirtArea<Tracked> {
         let mut cursor = page_table
             .cursor_mut(&preempt_guard, &cursor_range)
             .unwrap();
-        for page in pages.into_iter() {
+        for (page, prop) in pages.into_iter() {
             // SAFETY: The constructor of the `KVirtArea<Tracked>` structure
             // has already ensured that this mapping does not affect kernel's
             // memory safety.
@@ -11,7 +11,7 @@
 //!
 //! ```text
 //! +-+ <- the highest used address (0xffff_ffff_ffff_0000)
-//! | |         For the kernel code, 1 GiB. Mapped frames are tracked.
+//! | |         For the kernel code, 1 GiB. Mapped frames are untracked.
 //! +-+ <- 0xffff_ffff_8000_0000
 //! | |
 //! | |         Unused hole.
@@ -38,6 +38,7 @@
 
 pub(crate) mod kvirt_area;
 
+use alloc::{vec, vec::Vec};
 use core::ops::Range;
 
 use log::info;
@@ -47,12 +48,12 @@ mod test;
 
 use super::{
     frame::{
-        meta::{mapping, KernelMeta, MetaPageMeta},
-        Frame, Segment,
+        meta::{mapping, MetaPageMeta},
+        Segment,
     },
     page_prop::{CachePolicy, PageFlags, PageProperty, PrivilegedPageFlags},
     page_table::{KernelMode, PageTable},
-    Paddr, PagingConstsTrait, Vaddr, PAGE_SIZE,
+    Paddr, PagingConstsTrait, Vaddr,
 };
 use crate::{
     arch::mm::{PageTableEntry, PagingConsts},
@@ -85,6 +86,8 @@ const KERNEL_CODE_BASE_VADDR: usize = 0xffff_ffff_8000_0000 << ADDR_WIDTH_SHIFT;
 #[cfg(target_arch = "riscv64")]
 const KERNEL_CODE_BASE_VADDR: usize = 0xffff_ffff_0000_0000 << ADDR_WIDTH_SHIFT;
 
+const KERNEL_CODE_VADDR_RANGE: Range<Vaddr> = KERNEL_CODE_BASE_VADDR..KERNEL_END_VADDR;
+
 const FRAME_METADATA_CAP_VADDR: Vaddr = 0xffff_e100_0000_0000 << ADDR_WIDTH_SHIFT;
 const FRAME_METADATA_BASE_VADDR: Vaddr = 0xffff_e000_0000_0000 << ADDR_WIDTH_SHIFT;
 pub(in crate::mm) const FRAME_METADATA_RANGE: Range<Vaddr> =
@@ -112,13 +115,21 @@ pub fn paddr_to_vaddr(pa: Paddr) -> usize {
 ///
 /// About what is tracked mapping, see [`crate::mm::frame::meta::MapTrackingStatus`].
 pub(crate) fn should_map_as_tracked(addr: Vaddr) -> bool {
-    !(LINEAR_MAPPING_VADDR_RANGE.contains(&addr) || VMALLOC_VADDR_RANGE.contains(&addr))
+    !(LINEAR_MAPPING_VADDR_RANGE.contains(&addr)
+        || VMALLOC_VADDR_RANGE.contains(&addr)
+        || KERNEL_CODE_VADDR_RANGE.contains(&addr))
+}
+
+/// Returns whether the address is in the code of the kernel image.
+pub(crate) fn is_kernel_text(addr: Vaddr) -> bool {
+    KernelSections::get().text.contains(&addr)
 }
 
 /// The kernel page table instance.
 ///
 /// It manages the kernel mapping of all address spaces by sharing the kernel part. And it
 /// is unlikely to be activated.
+#[link_section = ".ro_after_init"]
 pub static KERNEL_PAGE_TABLE: Once<PageTable<KernelMode, PageTableEntry, PagingConsts>> =
     Once::new();
 
@@ -189,7 +200,10 @@ pub fn init_kernel_page_table(meta_pages: Segment<MetaPageMeta>) {
     }
 
     // Map for the kernel code itself.
-    // TODO: set separated permissions for each segments in the kernel.
+    //
+    // The frames are marked as used by the kernel at `super::frame::meta::init`
+    // and are never freed, so they are mapped as untracked. This allows each
+    // section to be mapped with huge pages if it is aligned.
     {
         let regions = &crate::boot::EARLY_INFO.get().unwrap().memory_regions;
         let region = regions
@@ -197,20 +211,17 @@ pub fn init_kernel_page_table(meta_pages: Segment<MetaPageMeta>) {
             .find(|r| r.typ() == MemoryRegionType::Kernel)
             .unwrap();
         let offset = kernel_loaded_offset();
-        let to = region.base()..region.end();
-        let from = to.start + offset..to.end + offset;
-        let prop = PageProperty {
-            flags: PageFlags::RWX,
-            cache: CachePolicy::Writeback,
-            priv_flags: PrivilegedPageFlags::GLOBAL,
-        };
-        let mut cursor = kpt.cursor_mut(&preempt_guard, &from).unwrap();
-        for frame_paddr in to.step_by(PAGE_SIZE) {
-            // SAFETY: They were initialized at `super::frame::meta::init`.
-            let page = unsafe { Frame::<KernelMeta>::from_raw(frame_paddr) };
+        let sections = KernelSections::get();
+        for from in sections.split(region.base() + offset..region.end() + offset) {
+            let to = from.start - offset..from.end - offset;
+            let prop = PageProperty {
+                flags: sections.flags_of(from.start),
+                cache: CachePolicy::Writeback,
+                priv_flags: PrivilegedPageFlags::GLOBAL,
+            };
             // SAFETY: we are doing mappings for the kernel.
             unsafe {
-                let _old = cursor.map(page.into(), prop);
+                kpt.map(&from, &to, prop).unwrap();
             }
         }
     }
@@ -218,6 +229,119 @@ pub fn init_kernel_page_table(meta_pages: Segment<MetaPageMeta>) {
     KERNEL_PAGE_TABLE.call_once(|| kpt);
 }
 
+/// The page-aligned boundaries of the sections in the kernel image.
+///
+/// The addresses are provided by the linker script.
+struct KernelSections {
+    text: Range<Vaddr>,
+    rodata: Range<Vaddr>,
+    ro_after_init: Range<Vaddr>,
+}
+
+impl KernelSections {
+    fn get() -> Self {
+        extern "C" {
+            fn __stext();
+            fn __srodata();
+            fn __sdata();
+            fn __ro_after_init_start();
+            fn __ro_after_init_end();
+        }
+        Self {
+            text: __stext as usize..__srodata as usize,
+            rodata: __srodata as usize..__sdata as usize,
+            ro_after_init: __ro_after_init_start as usize..__ro_after_init_end as usize,
+        }
+    }
+
+    /// Splits the virtual address range of the kernel image into the pieces
+    /// that have the same page flags.
+    fn split(&self, range: Range<Vaddr>) -> Vec<Range<Vaddr>> {
+        let mut boundaries = vec![range.start, range.end];
+        for section in [&self.text, &self.rodata, &self.ro_after_init] {
+            for addr in [section.start, section.end] {
+                if range.contains(&addr) {
+                    boundaries.push(addr);
+                }
+            }
+        }
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        boundaries
+            .windows(2)
+            .map(|window| window[0]..window[1])
+            .collect()
+    }
+
+    /// Returns the page flags of the kernel page at the virtual address.
+    ///
+    /// No kernel page is both writable and executable. The pages before the
+    /// text section hold the boot headers, the boot code and the boot stack,
+    /// which are no longer executed via the kernel page table, so they are
+    /// mapped as writable data.
+    ///
+    /// The data in the `.ro_after_init` section is only written before the
+    /// kernel page table is activated, so it is mapped read-only.
+    fn flags_of(&self, vaddr: Vaddr) -> PageFlags {
+        if self.text.contains(&vaddr) {
+            PageFlags::RX
+        } else if self.rodata.contains(&vaddr) || self.ro_after_init.contains(&vaddr) {
+            PageFlags::R
+        } else {
+            PageFlags::RW
+        }
+    }
+}
+
+/// Maps the hot-plugged physical memory into the kernel page table.
+///
+/// This adds the linear mapping of `prange` and maps `meta_pages`, which
+/// hold the metadata slots of the frames in `prange`, to the corresponding
+/// position in the frame metadata area.
+pub(in crate::mm) fn map_hotplugged_memory(
+    prange: &Range<Paddr>,
+    meta_pages: Segment<MetaPageMeta>,
+) -> crate::prelude::Result<()> {
+    if prange.end > VMALLOC_BASE_VADDR - LINEAR_MAPPING_BASE_VADDR {
+        return Err(crate::Error::InvalidArgs);
+    }
+
+    let kpt = KERNEL_PAGE_TABLE
+        .get()
+        .expect("The kernel page table is not initialized yet");
+    let preempt_guard = disable_preempt();
+    let prop = PageProperty {
+        flags: PageFlags::RW,
+        cache: CachePolicy::Writeback,
+        priv_flags: PrivilegedPageFlags::GLOBAL,
+    };
+
+    // Map the metadata pages first so that the frames are never reachable
+    // from the linear mapping without metadata.
+    {
+        let start_va = mapping::frame_to_meta::<PagingConsts>(prange.start);
+        let from = start_va..start_va + meta_pages.size();
+        let mut cursor = kpt.cursor_mut(&preempt_guard, &from)?;
+        for meta_page in meta_pages {
+            // SAFETY: we are doing the metadata mappings for the kernel.
+            unsafe {
+                let _old = cursor.map(meta_page.into(), prop);
+            }
+        }
+    }
+
+    {
+        let from = LINEAR_MAPPING_BASE_VADDR + prange.start..LINEAR_MAPPING_BASE_VADDR + prange.end;
+        // SAFETY: we are doing the linear mapping for the kernel.
+        unsafe {
+            kpt.map(&from, prange, prop)?;
+        }
+    }
+
+    Ok(())
+}
+
 /// Activates the kernel page table.
 ///
 /// # Safety
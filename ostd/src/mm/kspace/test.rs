@@ -1,16 +1,22 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::sync::atomic::AtomicU8;
+
+use align_ext::AlignExt;
+
 use crate::{
     mm::{
         kspace::{
             kvirt_area::{KVirtArea, Tracked, Untracked},
-            paddr_to_vaddr, should_map_as_tracked, LINEAR_MAPPING_BASE_VADDR,
-            TRACKED_MAPPED_PAGES_RANGE, VMALLOC_VADDR_RANGE,
+            paddr_to_vaddr, should_map_as_tracked, KernelSections, KERNEL_PAGE_TABLE,
+            LINEAR_MAPPING_BASE_VADDR, TRACKED_MAPPED_PAGES_RANGE, VMALLOC_VADDR_RANGE,
         },
-        page_prop::PageProperty,
+        page_prop::{PageFlags, PageProperty},
+        page_table::PageTableItem,
         Frame, FrameAllocOptions, Paddr, PAGE_SIZE,
     },
     prelude::*,
+    task::disable_preempt,
 };
 
 #[ktest]
@@ -112,3 +118,47 @@ fn map_as_tracked() {
     assert!(should_map_as_tracked(tracked_addr));
     assert!(!should_map_as_tracked(untracked_addr));
 }
+
+#[ktest]
+fn kernel_image_permissions() {
+    static DATA: AtomicU8 = AtomicU8::new(0);
+    let rwx = PageFlags::RWX;
+    let kpt = KERNEL_PAGE_TABLE.get().unwrap();
+
+    let text_flags = kpt
+        .query(kernel_image_permissions as usize)
+        .unwrap()
+        .1
+        .flags;
+    assert_eq!(text_flags & rwx, PageFlags::RX);
+
+    let ro_after_init_flags = kpt.query(kpt as *const _ as usize).unwrap().1.flags;
+    assert_eq!(ro_after_init_flags & rwx, PageFlags::R);
+
+    let data_flags = kpt.query(&DATA as *const _ as usize).unwrap().1.flags;
+    assert_eq!(data_flags & rwx, PageFlags::RW);
+}
+
+#[ktest]
+fn kernel_image_huge_pages() {
+    const HUGE_PAGE_SIZE: usize = 512 * PAGE_SIZE;
+
+    let text = KernelSections::get().text;
+    let start = text.start.align_up(HUGE_PAGE_SIZE);
+    if start + HUGE_PAGE_SIZE > text.end {
+        // The text section is too small to contain an aligned huge page.
+        return;
+    }
+
+    let kpt = KERNEL_PAGE_TABLE.get().unwrap();
+    let preempt_guard = disable_preempt();
+    let mut cursor = kpt
+        .cursor(&preempt_guard, &(start..start + HUGE_PAGE_SIZE))
+        .unwrap();
+    let PageTableItem::MappedUntracked { va, len, prop, .. } = cursor.query().unwrap() else {
+        panic!("the kernel text is not mapped as untracked pages");
+    };
+    assert_eq!(va, start);
+    assert_eq!(len, HUGE_PAGE_SIZE);
+    assert_eq!(prop.flags & PageFlags::RWX, PageFlags::RX);
+}
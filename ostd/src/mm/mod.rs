@@ -37,6 +37,7 @@ pub use self::{
         VmWriter,
     },
     page_prop::{CachePolicy, PageFlags, PageProperty},
+    page_table::nr_page_table_pages,
     vm_space::VmSpace,
 };
 pub(crate) use self::{
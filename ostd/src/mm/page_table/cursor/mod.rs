@@ -41,7 +41,7 @@ use super::{
 use crate::{
     mm::{
         frame::{meta::AnyFrameMeta, Frame},
-        Paddr, PageProperty, Vaddr,
+        Paddr, PageFlags, PageProperty, Vaddr,
     },
     task::atomic_mode::InAtomicMode,
 };
@@ -346,6 +346,7 @@ impl<'rcu, M: PageTableMode, E: PageTableEntryTrait, C: PagingConstsTrait>
     ) -> Option<Frame<dyn AnyFrameMeta>> {
         let end = self.0.va + frame.size();
         assert!(end <= self.0.barrier_va.end);
+        assert_w_xor_x::<M>(&prop);
 
         let rcu_guard = self.0.rcu_guard;
 
@@ -432,6 +433,7 @@ impl<'rcu, M: PageTableMode, E: PageTableEntryTrait, C: PagingConstsTrait>
         let end = self.0.va + pa.len();
         let mut pa = pa.start;
         assert!(end <= self.0.barrier_va.end);
+        assert_w_xor_x::<M>(&prop);
 
         let rcu_guard = self.0.rcu_guard;
 
@@ -694,7 +696,10 @@ impl<'rcu, M: PageTableMode, E: PageTableEntryTrait, C: PagingConstsTrait>
             }
 
             // Protect the current page.
-            cur_entry.protect(op);
+            cur_entry.protect(&mut |prop: &mut PageProperty| {
+                op(prop);
+                assert_w_xor_x::<M>(prop);
+            });
 
             let protected_va = self.0.va..self.0.va + page_size::<C>(self.0.level);
             self.0.move_forward();
@@ -794,6 +799,19 @@ impl<'rcu, M: PageTableMode, E: PageTableEntryTrait, C: PagingConstsTrait>
     }
 }
 
+/// Asserts that a kernel mapping is never both writable and executable.
+///
+/// User mappings are not restricted since user programs (e.g., JIT compilers)
+/// may legally request such mappings.
+fn assert_w_xor_x<M: PageTableMode>(prop: &PageProperty) {
+    if TypeId::of::<M>() == TypeId::of::<KernelMode>() {
+        assert!(
+            !prop.flags.contains(PageFlags::W | PageFlags::X),
+            "Mapping kernel memory as both writable and executable"
+        );
+    }
+}
+
 fn should_map_as_tracked<M: PageTableMode>(va: Vaddr) -> bool {
     (TypeId::of::<M>() == TypeId::of::<KernelMode>()
         || TypeId::of::<M>() == TypeId::of::<UserMode>())
@@ -24,6 +24,7 @@ use node::*;
 pub mod cursor;
 pub(crate) use cursor::PageTableItem;
 pub use cursor::{Cursor, CursorMut};
+pub use node::nr_page_table_pages;
 #[cfg(ktest)]
 mod test;
 
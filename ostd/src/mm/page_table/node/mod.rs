@@ -32,7 +32,7 @@ use core::{
     cell::SyncUnsafeCell,
     marker::PhantomData,
     ops::Deref,
-    sync::atomic::{AtomicU8, Ordering},
+    sync::atomic::{AtomicU8, AtomicUsize, Ordering},
 };
 
 pub(in crate::mm) use self::{child::Child, entry::Entry};
@@ -47,6 +47,17 @@ use crate::{
     task::atomic_mode::InAtomicMode,
 };
 
+/// The number of the frames that are allocated as page table nodes.
+static NR_PAGE_TABLE_PAGES: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the number of the frames that are allocated as page table nodes.
+///
+/// The page tables that are built at the boot time before the frame metadata is
+/// initialized are not counted.
+pub fn nr_page_table_pages() -> usize {
+    NR_PAGE_TABLE_PAGES.load(Ordering::Relaxed)
+}
+
 /// A smart pointer to a page table node.
 ///
 /// This smart pointer is an owner of a page table node. Thus creating and
@@ -76,6 +87,7 @@ impl<E: PageTableEntryTrait, C: PagingConstsTrait> PageTableNode<E, C> {
             .expect("Failed to allocate a page table node");
         // The allocated frame is zeroed. Make sure zero is absent PTE.
         debug_assert!(E::new_absent().as_bytes().iter().all(|&b| b == 0));
+        NR_PAGE_TABLE_PAGES.fetch_add(1, Ordering::Relaxed);
 
         frame
     }
@@ -318,6 +330,8 @@ impl<E: PageTableEntryTrait, C: PagingConstsTrait> PageTablePageMeta<E, C> {
 // accessed as untyped memory.
 unsafe impl<E: PageTableEntryTrait, C: PagingConstsTrait> AnyFrameMeta for PageTablePageMeta<E, C> {
     fn on_drop(&mut self, reader: &mut VmReader<Infallible>) {
+        NR_PAGE_TABLE_PAGES.fetch_sub(1, Ordering::Relaxed);
+
         let nr_children = self.nr_children.get_mut();
 
         if *nr_children == 0 {
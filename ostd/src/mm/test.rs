@@ -9,7 +9,7 @@ use crate::{
     mm::{
         io::{VmIo, VmReader, VmWriter},
         tlb::TlbFlushOp,
-        vm_space::{get_activated_vm_space, VmItem},
+        vm_space::{enter_lazy_tlb, get_activated_vm_space, VmItem},
         CachePolicy, FallibleVmRead, FallibleVmWrite, FrameAllocOptions, PageFlags, PageProperty,
         UFrame, VmSpace,
     },
@@ -404,6 +404,30 @@ mod io {
         assert_eq!(buffer, [0u8; 8]);
     }
 
+    /// Tests the `atomic_compare_exchange` method in Fallible mode.
+    #[ktest]
+    fn atomic_compare_exchange_fallible() {
+        let mut buffer = [0u32; 2];
+        let writer = VmWriter::from(buffer.as_bytes_mut());
+        let writer_fallible = writer.to_fallible();
+
+        assert_eq!(writer_fallible.atomic_compare_exchange(0, 42), Ok(0));
+        assert_eq!(writer_fallible.atomic_compare_exchange(0, 43), Ok(42));
+        assert_eq!(buffer[0], 42);
+
+        // The value must be aligned and fit in the writer.
+        let mut writer = VmWriter::from(&mut buffer.as_bytes_mut()[1..]).to_fallible();
+        assert_eq!(
+            writer.atomic_compare_exchange(0, 1),
+            Err(Error::InvalidArgs)
+        );
+        writer.skip(3);
+        assert_eq!(
+            writer.atomic_compare_exchange(0, 1),
+            Err(Error::InvalidArgs)
+        );
+    }
+
     /// Tests handling invalid arguments in Fallible mode.
     #[ktest]
     fn invalid_args_read_write_fallible() {
@@ -801,6 +825,16 @@ mod vmspace {
         let writer_result = vmspace.writer(0x4000, 0x1000);
         assert!(writer_result.is_ok());
 
+        // Fails in the lazy TLB mode, where the TLB may be stale.
+        enter_lazy_tlb();
+        assert!(vmspace.reader(0x4000, 0x1000).is_err());
+        assert!(vmspace.writer(0x4000, 0x1000).is_err());
+
+        // Succeeds after leaving the lazy TLB mode.
+        vmspace.activate();
+        assert!(vmspace.reader(0x4000, 0x1000).is_ok());
+        assert!(vmspace.writer(0x4000, 0x1000).is_ok());
+
         // Attempts to create a reader with an out-of-range address.
         let reader_result = vmspace.reader(0x4000, usize::MAX);
         assert!(reader_result.is_err());
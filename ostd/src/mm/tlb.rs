@@ -5,7 +5,7 @@
 use alloc::vec::Vec;
 use core::{
     ops::Range,
-    sync::atomic::{AtomicBool, Ordering},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
 };
 
 use super::{
@@ -14,7 +14,7 @@ use super::{
 };
 use crate::{
     arch::irq,
-    cpu::{AtomicCpuSet, CpuSet, PinCurrentCpu},
+    cpu::{AtomicCpuSet, CpuId, CpuSet, PinCurrentCpu},
     cpu_local,
     sync::{LocalIrqDisabled, SpinLock},
 };
@@ -24,6 +24,9 @@ use crate::{
 /// The flusher needs to stick to the current CPU.
 pub struct TlbFlusher<'a, G: PinCurrentCpu> {
     target_cpus: &'a AtomicCpuSet,
+    /// The generation of the TLB flushes of the target address space, if the
+    /// CPUs in the lazy TLB mode can be skipped.
+    tlb_gen: Option<&'a AtomicU64>,
     have_unsynced_flush: CpuSet,
     ops_stack: OpsStack,
     _pin_current: G,
@@ -40,12 +43,24 @@ impl<'a, G: PinCurrentCpu> TlbFlusher<'a, G> {
     pub fn new(target_cpus: &'a AtomicCpuSet, pin_current_guard: G) -> Self {
         Self {
             target_cpus,
+            tlb_gen: None,
             have_unsynced_flush: CpuSet::new_empty(),
             ops_stack: OpsStack::new(),
             _pin_current: pin_current_guard,
         }
     }
 
+    /// Allows the flusher to skip the CPUs in the lazy TLB mode.
+    ///
+    /// The `tlb_gen` is the generation of the TLB flushes of the address
+    /// space. It is advanced for each skipped flush so that the skipped CPUs
+    /// can flush their TLBs when leaving the lazy TLB mode. See
+    /// [`enter_lazy_tlb`] for details.
+    pub(crate) fn with_lazy_tlb(mut self, tlb_gen: &'a AtomicU64) -> Self {
+        self.tlb_gen = Some(tlb_gen);
+        self
+    }
+
     /// Issues a pending TLB flush request.
     ///
     /// This function does not guarantee to flush the TLB entries on either
@@ -95,7 +110,19 @@ impl<'a, G: PinCurrentCpu> TlbFlusher<'a, G> {
             need_flush_on_self = true;
         }
 
+        // Freed page tables may be walked speculatively via the stale paging
+        // structure caches, so all the CPUs must be flushed in that case.
+        let can_skip_lazy = !self.ops_stack.frees_page_tables;
+
+        let mut ipi_cpus = CpuSet::new_empty();
         for cpu in target_cpus.iter() {
+            if let Some(tlb_gen) = self.tlb_gen {
+                if can_skip_lazy && try_skip_lazy_cpu(tlb_gen, cpu) {
+                    continue;
+                }
+            }
+
+            ipi_cpus.add(cpu);
             {
                 let mut flush_ops = FLUSH_OPS.get_on_cpu(cpu).lock();
                 flush_ops.push_from(&self.ops_stack);
@@ -108,7 +135,8 @@ impl<'a, G: PinCurrentCpu> TlbFlusher<'a, G> {
             self.have_unsynced_flush.add(cpu);
         }
 
-        crate::smp::inter_processor_call(&target_cpus, do_remote_flush);
+        // All the requests are sent with a single IPI to each CPU.
+        crate::smp::inter_processor_call(&ipi_cpus, do_remote_flush);
 
         // Flush ourselves after sending all IPIs to save some time.
         if need_flush_on_self {
@@ -173,6 +201,21 @@ impl TlbFlushOp {
         }
     }
 
+    /// Merges the operation with the next one if they flush adjacent ranges.
+    fn try_merge(&self, next: &TlbFlushOp) -> Option<Self> {
+        let range = self.as_range()?;
+        let next_range = next.as_range()?;
+        (range.end == next_range.start).then(|| TlbFlushOp::Range(range.start..next_range.end))
+    }
+
+    fn as_range(&self) -> Option<Range<Vaddr>> {
+        match self {
+            TlbFlushOp::All => None,
+            TlbFlushOp::Address(addr) => Some(*addr..*addr + PAGE_SIZE),
+            TlbFlushOp::Range(range) => Some(range.clone()),
+        }
+    }
+
     fn optimize_for_large_range(self) -> Self {
         match self {
             TlbFlushOp::Range(range) => {
@@ -192,6 +235,86 @@ cpu_local! {
     static FLUSH_OPS: SpinLock<OpsStack, LocalIrqDisabled> = SpinLock::new(OpsStack::new());
     /// Whether this CPU finishes the last remote flush request.
     static ACK_REMOTE_FLUSH: AtomicBool = AtomicBool::new(true);
+    /// The sequence number of the lazy TLB periods of this CPU.
+    ///
+    /// It is odd if this CPU is in the lazy TLB mode, and is increased when
+    /// entering or leaving the mode.
+    static LAZY_TLB_SEQ: AtomicU64 = AtomicU64::new(0);
+    /// The TLB flush generation of the activated address space when this CPU
+    /// enters the lazy TLB mode.
+    static LAZY_TLB_GEN: AtomicU64 = AtomicU64::new(0);
+}
+
+/// Makes the current CPU enter the lazy TLB mode.
+///
+/// In the lazy TLB mode, the CPU keeps the page table of an address space
+/// activated, but runs tasks that never access the address space, e.g.,
+/// kernel threads. The remote TLB flushes of the address space are skipped
+/// for such CPUs, because the stale TLB entries are never used until the CPU
+/// leaves the mode via [`leave_lazy_tlb`].
+///
+/// The `tlb_gen` is the generation of the TLB flushes of the address space.
+/// Instead of sending a flush request to the CPU, a flusher advances the
+/// generation. So the CPU knows that it misses some flushes if the generation
+/// is changed when leaving the mode.
+pub(crate) fn enter_lazy_tlb(tlb_gen: &AtomicU64, pin_guard: &impl PinCurrentCpu) {
+    let cpu = pin_guard.current_cpu();
+    let seq = LAZY_TLB_SEQ.get_on_cpu(cpu);
+    if seq.load(Ordering::Relaxed) % 2 == 1 {
+        return;
+    }
+
+    // This must come before entering the mode. See `try_skip_lazy_cpu`.
+    LAZY_TLB_GEN
+        .get_on_cpu(cpu)
+        .store(tlb_gen.load(Ordering::SeqCst), Ordering::Relaxed);
+    seq.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Makes the current CPU leave the lazy TLB mode.
+///
+/// The `tlb_gen` is the generation of the TLB flushes of the address space
+/// that stays activated. This function returns `true` if some flushes are
+/// skipped for the CPU, in which case the caller must flush the TLB.
+pub(crate) fn leave_lazy_tlb(tlb_gen: &AtomicU64, pin_guard: &impl PinCurrentCpu) -> bool {
+    let cpu = pin_guard.current_cpu();
+    let seq = LAZY_TLB_SEQ.get_on_cpu(cpu);
+    if seq.load(Ordering::Relaxed) % 2 == 0 {
+        return false;
+    }
+
+    // This must come before checking the generation. See `try_skip_lazy_cpu`.
+    seq.fetch_add(1, Ordering::SeqCst);
+    tlb_gen.load(Ordering::SeqCst) != LAZY_TLB_GEN.get_on_cpu(cpu).load(Ordering::Relaxed)
+}
+
+/// Returns whether the current CPU is in the lazy TLB mode.
+pub(crate) fn is_in_lazy_tlb(pin_guard: &impl PinCurrentCpu) -> bool {
+    LAZY_TLB_SEQ
+        .get_on_cpu(pin_guard.current_cpu())
+        .load(Ordering::Relaxed)
+        % 2
+        == 1
+}
+
+/// Tries to skip the remote TLB flush of the CPU if it is in the lazy TLB mode.
+///
+/// If the flush is skipped, the generation is advanced in the same lazy TLB
+/// period of the CPU. Since the CPU records the generation before entering the
+/// mode, and reads it after leaving the mode, it will always see the advanced
+/// generation and flush its TLB.
+fn try_skip_lazy_cpu(tlb_gen: &AtomicU64, cpu: CpuId) -> bool {
+    let seq = LAZY_TLB_SEQ.get_on_cpu(cpu);
+
+    let period = seq.load(Ordering::SeqCst);
+    if period % 2 == 0 {
+        return false;
+    }
+
+    tlb_gen.fetch_add(1, Ordering::SeqCst);
+
+    // If the CPU has left the mode, it may have missed the advanced generation.
+    seq.load(Ordering::SeqCst) == period
 }
 
 fn do_remote_flush() {
@@ -226,6 +349,8 @@ struct OpsStack {
     need_flush_all: bool,
     size: usize,
     page_keeper: Vec<Frame<dyn AnyFrameMeta>>,
+    /// Whether any of the kept frames is a page table, i.e., not untyped.
+    frees_page_tables: bool,
 }
 
 impl OpsStack {
@@ -235,6 +360,7 @@ impl OpsStack {
             need_flush_all: false,
             size: 0,
             page_keeper: Vec::new(),
+            frees_page_tables: false,
         }
     }
 
@@ -244,12 +370,27 @@ impl OpsStack {
 
     fn push(&mut self, op: TlbFlushOp, drop_after_flush: Option<Frame<dyn AnyFrameMeta>>) {
         if let Some(frame) = drop_after_flush {
+            self.frees_page_tables |= !frame.dyn_meta().is_untyped();
             self.page_keeper.push(frame);
         }
 
         if self.need_flush_all {
             return;
         }
+
+        // Unmapping consecutive pages issues requests of consecutive addresses.
+        // Merge them into one range so that they do not exceed the threshold.
+        let op = match self.size.checked_sub(1).map(|last| &self.ops[last]) {
+            Some(Some(last_op)) => match last_op.try_merge(&op) {
+                Some(merged) => {
+                    self.size -= 1;
+                    merged
+                }
+                None => op,
+            },
+            _ => op,
+        };
+
         let op = op.optimize_for_large_range();
         if op == TlbFlushOp::All || self.size >= FLUSH_ALL_OPS_THRESHOLD {
             self.need_flush_all = true;
@@ -263,6 +404,7 @@ impl OpsStack {
 
     fn push_from(&mut self, other: &OpsStack) {
         self.page_keeper.extend(other.page_keeper.iter().cloned());
+        self.frees_page_tables |= other.frees_page_tables;
 
         if self.need_flush_all {
             return;
@@ -297,5 +439,48 @@ impl OpsStack {
         self.need_flush_all = false;
         self.size = 0;
         self.page_keeper.clear();
+        self.frees_page_tables = false;
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+    use crate::prelude::ktest;
+
+    #[ktest]
+    fn merge_consecutive_addresses() {
+        let nr_pages = FLUSH_ALL_RANGE_THRESHOLD / PAGE_SIZE;
+
+        let mut ops_stack = OpsStack::new();
+        for i in 0..nr_pages {
+            ops_stack.push(TlbFlushOp::Address(i * PAGE_SIZE), None);
+        }
+        assert!(!ops_stack.need_flush_all);
+        assert_eq!(ops_stack.size, 1);
+        assert_eq!(
+            ops_stack.ops[0],
+            Some(TlbFlushOp::Range(0..nr_pages * PAGE_SIZE))
+        );
+
+        // A non-consecutive address is not merged.
+        ops_stack.push(TlbFlushOp::Address((nr_pages + 1) * PAGE_SIZE), None);
+        assert_eq!(ops_stack.size, 2);
+
+        ops_stack.push(TlbFlushOp::Address((nr_pages + 2) * PAGE_SIZE), None);
+        assert_eq!(ops_stack.size, 2);
+        assert_eq!(
+            ops_stack.ops[1],
+            Some(TlbFlushOp::Range(
+                (nr_pages + 1) * PAGE_SIZE..(nr_pages + 3) * PAGE_SIZE
+            ))
+        );
+
+        // A merged range that is too large is turned into flushing all.
+        ops_stack.push(
+            TlbFlushOp::Range((nr_pages + 3) * PAGE_SIZE..nr_pages * 3 * PAGE_SIZE),
+            None,
+        );
+        assert!(ops_stack.need_flush_all);
     }
 }
@@ -9,17 +9,22 @@
 //! powerful concurrent accesses to the page table, and suffers from the same
 //! validity concerns as described in [`super::page_table::cursor`].
 
-use core::{ops::Range, sync::atomic::Ordering};
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 use crate::{
-    arch::mm::{current_page_table_paddr, PageTableEntry, PagingConsts},
+    arch::mm::{
+        current_page_table_paddr, tlb_flush_all_excluding_global, PageTableEntry, PagingConsts,
+    },
     cpu::{AtomicCpuSet, CpuSet, PinCurrentCpu},
     cpu_local_cell,
     mm::{
         io::Fallible,
         kspace::KERNEL_PAGE_TABLE,
         page_table::{self, PageTable, PageTableItem, UserMode},
-        tlb::{TlbFlushOp, TlbFlusher},
+        tlb::{self, TlbFlushOp, TlbFlusher},
         PageProperty, UFrame, VmReader, VmWriter, MAX_USERSPACE_VADDR,
     },
     prelude::*,
@@ -66,6 +71,9 @@ use crate::{
 pub struct VmSpace {
     pt: PageTable<UserMode>,
     cpus: AtomicCpuSet,
+    /// The generation of the TLB flushes that are skipped for the CPUs in the
+    /// lazy TLB mode. See [`enter_lazy_tlb`].
+    tlb_gen: AtomicU64,
 }
 
 impl VmSpace {
@@ -74,6 +82,7 @@ impl VmSpace {
         Self {
             pt: KERNEL_PAGE_TABLE.get().unwrap().create_user_page_table(),
             cpus: AtomicCpuSet::new(CpuSet::new_empty()),
+            tlb_gen: AtomicU64::new(0),
         }
     }
 
@@ -110,7 +119,7 @@ impl VmSpace {
     ) -> Result<CursorMut<'a>> {
         Ok(self.pt.cursor_mut(guard, va).map(|pt_cursor| CursorMut {
             pt_cursor,
-            flusher: TlbFlusher::new(&self.cpus, disable_preempt()),
+            flusher: TlbFlusher::new(&self.cpus, disable_preempt()).with_lazy_tlb(&self.tlb_gen),
         })?)
     }
 
@@ -122,6 +131,11 @@ impl VmSpace {
         let last_ptr = ACTIVATED_VM_SPACE.load();
 
         if last_ptr == Arc::as_ptr(self) {
+            // The page table is kept activated, but the TLB may be stale if
+            // some flushes are skipped in the lazy TLB mode.
+            if tlb::leave_lazy_tlb(&self.tlb_gen, &preempt_guard) {
+                tlb_flush_all_excluding_global();
+            }
             return;
         }
 
@@ -136,23 +150,41 @@ impl VmSpace {
             // SAFETY: The pointer is cast from an `Arc` when it's activated
             // the last time, so it can be restored and only restored once.
             let last = unsafe { Arc::from_raw(last_ptr) };
+            // The TLB entries of the last page table are flushed when
+            // activating the new one.
+            tlb::leave_lazy_tlb(&last.tlb_gen, &preempt_guard);
             last.cpus.remove(cpu, Ordering::Relaxed);
         }
 
         self.pt.activate();
     }
 
+    /// Checks whether the user space of the current task can be accessed via this `VmSpace`.
+    fn check_user_access(&self) -> Result<()> {
+        if current_page_table_paddr() != unsafe { self.pt.root_paddr() } {
+            return Err(Error::AccessDenied);
+        }
+
+        // The TLB may be stale in the lazy TLB mode, since the flushes of this `VmSpace` are
+        // skipped for the current CPU. The mode is left only by `activate`, so the current task
+        // is not a user task that owns this `VmSpace`.
+        if tlb::is_in_lazy_tlb(&disable_preempt()) {
+            return Err(Error::AccessDenied);
+        }
+
+        Ok(())
+    }
+
     /// Creates a reader to read data from the user space of the current task.
     ///
-    /// Returns `Err` if this `VmSpace` is not belonged to the user space of the current task
-    /// or the `vaddr` and `len` do not represent a user space memory range.
+    /// Returns `Err` if this `VmSpace` is not belonged to the user space of the current task,
+    /// the current CPU is in the lazy TLB mode (see [`enter_lazy_tlb`]), or the `vaddr` and
+    /// `len` do not represent a user space memory range.
     ///
     /// Users must ensure that no other page table is activated in the current task during the
     /// lifetime of the created `VmReader`. This guarantees that the `VmReader` can operate correctly.
     pub fn reader(&self, vaddr: Vaddr, len: usize) -> Result<VmReader<'_, Fallible>> {
-        if current_page_table_paddr() != unsafe { self.pt.root_paddr() } {
-            return Err(Error::AccessDenied);
-        }
+        self.check_user_access()?;
 
         if vaddr.checked_add(len).unwrap_or(usize::MAX) > MAX_USERSPACE_VADDR {
             return Err(Error::AccessDenied);
@@ -164,15 +196,14 @@ impl VmSpace {
 
     /// Creates a writer to write data into the user space.
     ///
-    /// Returns `Err` if this `VmSpace` is not belonged to the user space of the current task
-    /// or the `vaddr` and `len` do not represent a user space memory range.
+    /// Returns `Err` if this `VmSpace` is not belonged to the user space of the current task,
+    /// the current CPU is in the lazy TLB mode (see [`enter_lazy_tlb`]), or the `vaddr` and
+    /// `len` do not represent a user space memory range.
     ///
     /// Users must ensure that no other page table is activated in the current task during the
     /// lifetime of the created `VmWriter`. This guarantees that the `VmWriter` can operate correctly.
     pub fn writer(&self, vaddr: Vaddr, len: usize) -> Result<VmWriter<'_, Fallible>> {
-        if current_page_table_paddr() != unsafe { self.pt.root_paddr() } {
-            return Err(Error::AccessDenied);
-        }
+        self.check_user_access()?;
 
         if vaddr.checked_add(len).unwrap_or(usize::MAX) > MAX_USERSPACE_VADDR {
             return Err(Error::AccessDenied);
@@ -398,6 +429,31 @@ impl<'a> CursorMut<'a> {
     }
 }
 
+/// Makes the current CPU enter the lazy TLB mode.
+///
+/// This should be called when the current CPU switches to a task that has no
+/// user space, e.g., a kernel thread. The activated [`VmSpace`] stays
+/// activated to avoid switching page tables, but the TLB flushes of the
+/// `VmSpace` no longer interrupt the current CPU. If the `VmSpace` is
+/// activated again, the TLB is flushed if any of the flushes was skipped.
+///
+/// Until the next [`VmSpace::activate`], [`VmSpace::reader`] and
+/// [`VmSpace::writer`] fail on the current CPU, so the stale TLB entries are
+/// never used to access the user space.
+pub fn enter_lazy_tlb() {
+    let preempt_guard = disable_preempt();
+
+    let ptr = ACTIVATED_VM_SPACE.load();
+    if ptr.is_null() {
+        return;
+    }
+    // SAFETY: The pointer is cast from an `Arc` when it's activated, and the
+    // `Arc` is not dropped until another `VmSpace` is activated, which cannot
+    // happen since the preemption is disabled.
+    let vm_space = unsafe { &*ptr };
+    tlb::enter_lazy_tlb(&vm_space.tlb_gen, &preempt_guard);
+}
+
 cpu_local_cell! {
     /// The `Arc` pointer to the activated VM space on this CPU. If the pointer
     /// is NULL, it means that the activated page table is merely the kernel
@@ -2,10 +2,16 @@
 
 //! Panic support.
 
-use core::ffi::c_void;
+mod report;
+
+use core::{
+    ffi::c_void,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 pub use unwinding::panic::{begin_panic, catch_unwind};
 
+pub use self::report::{emit_oops_report, emit_panic_report};
 use crate::{
     arch::qemu::{exit_qemu, QemuExitCode},
     early_print, early_println,
@@ -44,9 +50,39 @@ pub fn __ostd_panic_handler(info: &core::panic::PanicInfo) -> ! {
     early_println!("Non-resettable panic! {:#?}", info);
 
     print_stack_trace();
+    emit_panic_report(info);
     abort();
 }
 
+/// The canary value checked by the stack-smashing protection.
+///
+/// The kernel is built with `-Z stack-protector=strong`, so the compiler
+/// places this value between the local buffers and the return address of
+/// vulnerable functions and checks it before the functions return.
+#[no_mangle]
+#[link_section = ".ro_after_init"]
+#[expect(non_upper_case_globals)]
+static __stack_chk_guard: AtomicUsize = AtomicUsize::new(0x595e_9fbd_94fd_a766);
+
+/// Randomizes the stack canary.
+///
+/// This function must be called by a function that never returns, before
+/// any function whose canary has been saved with the old value returns. It is
+/// always inlined so that it does not check the canary by itself.
+#[inline(always)]
+pub(crate) fn init_stack_protector() {
+    let random = crate::arch::read_random().unwrap_or_else(crate::arch::read_tsc);
+    // Keep a zero byte in the canary to stop string-based overflows.
+    let canary = (random as usize) & !0xff;
+    __stack_chk_guard.fetch_xor(canary, Ordering::Relaxed);
+}
+
+/// Called by functions that detect a corrupted stack canary.
+#[no_mangle]
+extern "C" fn __stack_chk_fail() -> ! {
+    panic!("Stack smashing detected");
+}
+
 /// Aborts the QEMU
 pub fn abort() -> ! {
     exit_qemu(QemuExitCode::Failed);
@@ -78,6 +114,11 @@ pub fn print_stack_trace() {
                 fde_initial_address,
                 pc,
             );
+            // The return address may be the start of the next function if the
+            // call is the last instruction, so the address before it is used.
+            if crate::kallsyms::is_available() {
+                early_println!("      at {}", crate::kallsyms::Symbolized(pc - 1));
+            }
         }
         // Print the first 8 general registers for any architecture. The register number follows
         // the DWARF standard.
@@ -0,0 +1,219 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Structured panic reports.
+//!
+//! A panic report collects the information about a kernel panic in a format
+//! that can be parsed by the runner of the kernel, e.g., OSDK. The report is
+//! written to the QEMU debug console if it exists (see
+//! `arch::qemu::write_debugcon`), or printed to the console otherwise.
+//!
+//! The report consists of lines, each of which starts with a keyword:
+//!
+//! ```text
+//! ---- BEGIN PANIC REPORT v1 ----
+//! message <a line of the panic message>
+//! location <file>:<line>:<column>
+//! cpu <the ID of the CPU>
+//! reg <the name of the register> <the value>
+//! frame <the PC of the stack frame> <the symbol of the PC, if available>
+//! log <a line of the recent log messages>
+//! ---- END PANIC REPORT ----
+//! ```
+//!
+//! The registers are those of the innermost stack frame when the report is
+//! emitted. The frames are listed from the innermost to the outermost.
+//!
+//! The panics that are recovered from (i.e., the kernel "oopses") are reported
+//! in the same format, except that the markers are `---- BEGIN OOPS REPORT v1
+//! ----` and `---- END OOPS REPORT ----`. Every oops is reported, while only
+//! the first panic is.
+
+use core::{
+    ffi::c_void,
+    fmt::{self, Write},
+    panic::PanicInfo,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use gimli::Register;
+use unwinding::abi::{
+    UnwindContext, UnwindReasonCode, _Unwind_Backtrace, _Unwind_GetGR, _Unwind_GetIP,
+};
+
+use crate::{arch::qemu::write_debugcon, cpu::current_cpu_racy, early_print, sync::SpinLock};
+
+/// The version of the report format.
+const VERSION: u32 = 1;
+
+/// The number of the general-purpose registers in the DWARF register numbers.
+#[cfg(target_arch = "x86_64")]
+const NR_GENERAL_REGS: u16 = 16;
+#[cfg(not(target_arch = "x86_64"))]
+const NR_GENERAL_REGS: u16 = 32;
+
+/// The maximum number of the stack frames in the report.
+const MAX_FRAMES: usize = 128;
+
+/// Emits the report of the panic.
+///
+/// Only the first panic is reported if multiple CPUs panic concurrently.
+pub fn emit_panic_report(info: &PanicInfo) {
+    static IS_REPORTED: AtomicBool = AtomicBool::new(false);
+    if IS_REPORTED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    let mut writer = ReportWriter;
+    let _ = writer.write_report("PANIC", info);
+}
+
+/// Emits the report of the panic that will be recovered from as an oops.
+///
+/// The reports of the concurrent oopses are not interleaved.
+pub fn emit_oops_report(info: &PanicInfo) {
+    static REPORT_LOCK: SpinLock<()> = SpinLock::new(());
+    let _guard = REPORT_LOCK.lock();
+
+    let mut writer = ReportWriter;
+    let _ = writer.write_report("OOPS", info);
+}
+
+/// A writer that writes to the debug console, or to the console if the
+/// debug console does not exist.
+struct ReportWriter;
+
+impl Write for ReportWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if !write_debugcon(s.as_bytes()) {
+            early_print!("{}", s);
+        }
+        Ok(())
+    }
+}
+
+impl ReportWriter {
+    fn write_report(&mut self, kind: &str, info: &PanicInfo) -> fmt::Result {
+        writeln!(self, "---- BEGIN {} REPORT v{} ----", kind, VERSION)?;
+
+        let mut message = LineWriter::new(self, "message");
+        write!(message, "{}", info.message())?;
+        message.finish()?;
+
+        if let Some(location) = info.location() {
+            writeln!(
+                self,
+                "location {}:{}:{}",
+                location.file(),
+                location.line(),
+                location.column()
+            )?;
+        }
+        writeln!(self, "cpu {}", current_cpu_racy().as_usize())?;
+
+        self.write_frames();
+
+        crate::logger::for_each_recent_log(|log| {
+            for line in log.lines() {
+                let _ = writeln!(self, "log {}", line);
+            }
+        });
+
+        writeln!(self, "---- END {} REPORT ----", kind)
+    }
+
+    fn write_frames(&mut self) {
+        struct CallbackData<'a> {
+            writer: &'a mut ReportWriter,
+            counter: usize,
+        }
+
+        extern "C" fn callback(
+            unwind_ctx: &UnwindContext<'_>,
+            arg: *mut c_void,
+        ) -> UnwindReasonCode {
+            // SAFETY: The argument is the pointer to the callback data.
+            let data = unsafe { &mut *(arg as *mut CallbackData) };
+            data.counter += 1;
+            if data.counter > MAX_FRAMES {
+                return UnwindReasonCode::END_OF_STACK;
+            }
+
+            if data.counter == 1 {
+                for i in 0..NR_GENERAL_REGS {
+                    let value = _Unwind_GetGR(unwind_ctx, i as i32);
+                    let _ = writeln!(data.writer, "reg {} {:#x}", register_name(i), value);
+                }
+            }
+
+            let pc = _Unwind_GetIP(unwind_ctx);
+            if pc > 0 {
+                let _ = write!(data.writer, "frame {:#x}", pc);
+                // The return address may be the start of the next function if the
+                // call is the last instruction, so the address before it is used.
+                if crate::kallsyms::is_available() {
+                    let _ = write!(data.writer, " {}", crate::kallsyms::Symbolized(pc - 1));
+                }
+                let _ = writeln!(data.writer);
+            }
+            UnwindReasonCode::NO_REASON
+        }
+
+        let mut data = CallbackData {
+            writer: self,
+            counter: 0,
+        };
+        _Unwind_Backtrace(callback, &mut data as *mut _ as _);
+    }
+}
+
+/// A writer that prefixes each line with a keyword.
+struct LineWriter<'a> {
+    writer: &'a mut ReportWriter,
+    keyword: &'static str,
+    is_line_start: bool,
+}
+
+impl<'a> LineWriter<'a> {
+    fn new(writer: &'a mut ReportWriter, keyword: &'static str) -> Self {
+        Self {
+            writer,
+            keyword,
+            is_line_start: true,
+        }
+    }
+
+    /// Terminates the last line.
+    fn finish(mut self) -> fmt::Result {
+        if self.is_line_start {
+            return Ok(());
+        }
+        self.write_str("\n")
+    }
+}
+
+impl Write for LineWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for line in s.split_inclusive('\n') {
+            if self.is_line_start {
+                write!(self.writer, "{} ", self.keyword)?;
+            }
+            self.writer.write_str(line)?;
+            self.is_line_start = line.ends_with('\n');
+        }
+        Ok(())
+    }
+}
+
+fn register_name(i: u16) -> &'static str {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "x86_64")] {
+            gimli::X86_64::register_name(Register(i)).unwrap_or("unknown")
+        } else if #[cfg(target_arch = "riscv64")] {
+            gimli::RiscV::register_name(Register(i)).unwrap_or("unknown")
+        } else if #[cfg(target_arch = "aarch64")] {
+            gimli::AArch64::register_name(Register(i)).unwrap_or("unknown")
+        } else {
+            "unknown"
+        }
+    }
+}
@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Power management.
+//!
+//! This module provides the ability to power off or restart the machine, as well as to get
+//! notified when the power button is pressed.
+//!
+//! Powering off or restarting the machine takes effect immediately. It is the responsibility of
+//! the caller to bring the system to a consistent state (e.g., by syncing the file systems and
+//! stopping the devices) before calling the functions here.
+
+use spin::Once;
+
+/// The exit code of the system.
+///
+/// The exit code is reported to the host if the machine is virtualized (e.g., via QEMU's debug
+/// exit device). Otherwise, it is ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// The code that indicates a successful exit.
+    Success,
+    /// The code that indicates a failed exit.
+    Failure,
+}
+
+/// Powers off the machine.
+///
+/// If the machine cannot be powered off, this function halts the current CPU forever.
+pub fn poweroff(code: ExitCode) -> ! {
+    log::info!("Powering off the machine with exit code {:?}", code);
+    crate::arch::power::poweroff(code)
+}
+
+/// Restarts the machine.
+///
+/// If the machine cannot be restarted, this function halts the current CPU forever.
+pub fn restart() -> ! {
+    log::info!("Restarting the machine");
+    crate::arch::power::restart()
+}
+
+static POWER_BUTTON_HANDLER: Once<fn()> = Once::new();
+
+/// Registers the handler that is called when the power button is pressed.
+///
+/// The handler is called in the interrupt context, so it should defer the heavy work (e.g.,
+/// shutting down the system) to a task. Only one handler can be registered. Subsequent
+/// registrations are ignored.
+///
+/// The handler is never called if the platform does not report power button events (e.g., if
+/// there are no ACPI tables).
+pub fn register_power_button_handler(handler: fn()) {
+    POWER_BUTTON_HANDLER.call_once(|| handler);
+}
+
+/// Notifies that the power button is pressed.
+///
+/// This should be called by the platform-specific code in the interrupt context.
+#[cfg_attr(not(target_arch = "x86_64"), expect(dead_code))]
+pub(crate) fn notify_power_button_pressed() {
+    log::info!("The power button is pressed");
+    if let Some(handler) = POWER_BUTTON_HANDLER.get() {
+        handler();
+    }
+}
@@ -24,10 +24,12 @@ use crate::task::{scheduler, Task};
 // Examples of `wake_up()`:
 //  - `WaitQueue::wake_one()`
 //  - `WaitQueue::wake_all()`
+//  - `WaitQueue::wake_nr()`
 //  - `Waker::wake_up()`
 //
 // Examples of `wait()`:
 //  - `WaitQueue::wait_until()`
+//  - `WaitQueue::wait_until_exclusive()`
 //  - `Waiter::wait()`
 //  - `Waiter::drop()`
 //
@@ -40,10 +42,23 @@ use crate::task::{scheduler, Task};
 /// Multiple threads may be the waiters of a wait queue.
 /// Other threads may invoke the `wake`-family methods of a wait queue to
 /// wake up one or many waiting threads.
+///
+/// # Exclusive waiters
+///
+/// A waiter may wait exclusively (via [`WaitQueue::wait_until_exclusive`]) if it is pointless to
+/// wake up more than one of such waiters for an event, e.g., multiple threads accepting
+/// connections on the same socket. [`WaitQueue::wake_nr`] wakes up all the non-exclusive waiters
+/// but only a limited number of the exclusive ones, which avoids the thundering herd problem.
 pub struct WaitQueue {
-    // A copy of `wakers.len()`, used for the lock-free fast path in `wake_one` and `wake_all`.
+    // A copy of `wakers.len()`, used for the lock-free fast path in the `wake`-family methods.
     num_wakers: AtomicU32,
-    wakers: SpinLock<VecDeque<Arc<Waker>>, LocalIrqDisabled>,
+    wakers: SpinLock<VecDeque<WaitEntry>, LocalIrqDisabled>,
+}
+
+/// An entry of a wait queue.
+struct WaitEntry {
+    waker: Arc<Waker>,
+    is_exclusive: bool,
 }
 
 impl WaitQueue {
@@ -84,6 +99,30 @@ impl WaitQueue {
             .unwrap()
     }
 
+    /// Waits exclusively until some condition is met.
+    ///
+    /// This method is the same as [`WaitQueue::wait_until`], except that the waiter is woken up
+    /// by [`WaitQueue::wake_nr`] only if the number of the exclusive waiters to wake is not
+    /// exhausted. See [the type-level documentation](WaitQueue#exclusive-waiters) for details.
+    #[track_caller]
+    pub fn wait_until_exclusive<F, R>(&self, mut cond: F) -> R
+    where
+        F: FnMut() -> Option<R>,
+    {
+        if let Some(res) = cond() {
+            return res;
+        }
+
+        let (waiter, _) = Waiter::new_pair();
+        let cond = || {
+            self.enqueue_exclusive(waiter.waker());
+            cond()
+        };
+        waiter
+            .wait_until_or_cancelled(cond, || Ok::<(), ()>(()))
+            .unwrap()
+    }
+
     /// Wakes up one waiting thread, if there is one at the point of time when this method is
     /// called, returning whether such a thread was woken up.
     pub fn wake_one(&self) -> bool {
@@ -94,7 +133,7 @@ impl WaitQueue {
 
         loop {
             let mut wakers = self.wakers.lock();
-            let Some(waker) = wakers.pop_front() else {
+            let Some(WaitEntry { waker, .. }) = wakers.pop_front() else {
                 return false;
             };
             self.num_wakers.fetch_sub(1, Ordering::Release);
@@ -118,7 +157,51 @@ impl WaitQueue {
 
         loop {
             let mut wakers = self.wakers.lock();
-            let Some(waker) = wakers.pop_front() else {
+            let Some(WaitEntry { waker, .. }) = wakers.pop_front() else {
+                break;
+            };
+            self.num_wakers.fetch_sub(1, Ordering::Release);
+            // Avoid holding lock when calling `wake_up`
+            drop(wakers);
+
+            if waker.wake_up() {
+                num_woken += 1;
+            }
+        }
+
+        num_woken
+    }
+
+    /// Wakes up all non-exclusive waiting threads and at most `nr_exclusive` exclusive waiting
+    /// threads, returning the number of threads that were woken up.
+    ///
+    /// The waiting threads are woken up in the order that they started waiting. An exclusive
+    /// waiter that has already been woken up or has stopped waiting is not counted, so a wake
+    /// event won't be lost in this case.
+    pub fn wake_nr(&self, mut nr_exclusive: usize) -> usize {
+        // Fast path
+        if self.is_empty() {
+            return 0;
+        }
+
+        let mut num_woken = 0;
+
+        loop {
+            let mut wakers = self.wakers.lock();
+            // The exclusive waiters beyond the limit are skipped and kept in the queue.
+            let index = if nr_exclusive > 0 {
+                0
+            } else {
+                let Some(index) = wakers.iter().position(|entry| !entry.is_exclusive) else {
+                    break;
+                };
+                index
+            };
+            let Some(WaitEntry {
+                waker,
+                is_exclusive,
+            }) = wakers.remove(index)
+            else {
                 break;
             };
             self.num_wakers.fetch_sub(1, Ordering::Release);
@@ -127,6 +210,9 @@ impl WaitQueue {
 
             if waker.wake_up() {
                 num_woken += 1;
+                if is_exclusive {
+                    nr_exclusive -= 1;
+                }
             }
         }
 
@@ -143,8 +229,21 @@ impl WaitQueue {
     /// Enqueues the input [`Waker`] to the wait queue.
     #[doc(hidden)]
     pub fn enqueue(&self, waker: Arc<Waker>) {
+        self.do_enqueue(waker, false);
+    }
+
+    /// Enqueues the input [`Waker`] to the wait queue as an exclusive waiter.
+    #[doc(hidden)]
+    pub fn enqueue_exclusive(&self, waker: Arc<Waker>) {
+        self.do_enqueue(waker, true);
+    }
+
+    fn do_enqueue(&self, waker: Arc<Waker>, is_exclusive: bool) {
         let mut wakers = self.wakers.lock();
-        wakers.push_back(waker);
+        wakers.push_back(WaitEntry {
+            waker,
+            is_exclusive,
+        });
         self.num_wakers.fetch_add(1, Ordering::Acquire);
     }
 }
@@ -173,6 +272,7 @@ impl !Sync for Waiter {}
 /// be used across different threads.
 pub struct Waker {
     has_woken: AtomicBool,
+    is_sleeping: AtomicBool,
     task: Arc<Task>,
 }
 
@@ -181,6 +281,7 @@ impl Waiter {
     pub fn new_pair() -> (Self, Arc<Waker>) {
         let waker = Arc::new(Waker {
             has_woken: AtomicBool::new(false),
+            is_sleeping: AtomicBool::new(false),
             task: Task::current().unwrap().cloned(),
         });
         let waiter = Self {
@@ -268,10 +369,28 @@ impl Waker {
         true
     }
 
+    /// Wakes up the associated [`Waiter`] if it is sleeping.
+    ///
+    /// This method returns `true` if the waiter is woken by this call. Unlike [`Waker::wake_up`],
+    /// it does nothing and returns `false` if the waiter is not sleeping, e.g., if it is running to
+    /// check its wake condition. This allows the caller to pass the wake event to another waiter
+    /// that is more likely to be idle, but the caller must eventually fall back to
+    /// [`Waker::wake_up`] to avoid missing the wake event.
+    pub fn wake_up_if_sleeping(&self) -> bool {
+        if !self.is_sleeping.load(Ordering::Relaxed) {
+            return false;
+        }
+        self.wake_up()
+    }
+
     #[track_caller]
     fn do_wait(&self) {
         while !self.has_woken.swap(false, Ordering::Acquire) {
+            self.is_sleeping.store(true, Ordering::Relaxed);
+            self.task.set_sleeping(true);
             scheduler::park_current(|| self.has_woken.load(Ordering::Acquire));
+            self.task.set_sleeping(false);
+            self.is_sleeping.store(false, Ordering::Relaxed);
         }
     }
 
@@ -326,6 +445,40 @@ mod test {
         });
     }
 
+    #[ktest]
+    fn queue_wake_nr() {
+        queue_wake(|queue| {
+            queue.wake_nr(1);
+        });
+    }
+
+    #[ktest]
+    fn queue_wake_nr_exclusive() {
+        let queue = WaitQueue::new();
+
+        let (waiter1, waker1) = Waiter::new_pair();
+        let (waiter2, waker2) = Waiter::new_pair();
+        let (waiter3, waker3) = Waiter::new_pair();
+        queue.enqueue_exclusive(waker1);
+        queue.enqueue(waker2);
+        queue.enqueue_exclusive(waker3.clone());
+
+        // The non-exclusive waiter and the first exclusive waiter are woken up.
+        assert_eq!(queue.wake_nr(1), 2);
+        waiter1.wait();
+        waiter2.wait();
+        assert!(!queue.is_empty());
+
+        // A dropped exclusive waiter does not consume the quota.
+        let (waiter4, waker4) = Waiter::new_pair();
+        queue.enqueue_exclusive(waker4);
+        drop(waiter3);
+        assert_eq!(queue.wake_nr(1), 1);
+        waiter4.wait();
+        assert!(queue.is_empty());
+        assert!(!waker3.wake_up());
+    }
+
     #[ktest]
     fn waiter_wake_twice() {
         let (_waiter, waker) = Waiter::new_pair();
@@ -46,6 +46,11 @@ pub fn might_sleep() {
     }
 }
 
+/// Returns whether the current task is executing in atomic mode.
+pub(crate) fn is_in_atomic_mode() -> bool {
+    super::preempt::cpu_local::get_guard_count() != 0 || !crate::arch::irq::is_local_enabled()
+}
+
 /// A marker trait for guard types that enforce the atomic mode.
 ///
 /// Key kernel primitives such as `SpinLock` and `Rcu` rely on
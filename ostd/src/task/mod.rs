@@ -13,12 +13,13 @@ use core::{
     any::Any,
     borrow::Borrow,
     cell::{Cell, SyncUnsafeCell},
-    ops::Deref,
+    ops::{Deref, Range},
     ptr::NonNull,
-    sync::atomic::AtomicBool,
+    sync::atomic::{AtomicBool, Ordering},
 };
 
-use kernel_stack::KernelStack;
+use align_ext::AlignExt;
+use kernel_stack::{KernelStack, KERNEL_STACK_SIZE};
 use processor::current_task;
 use spin::Once;
 use utils::ForceSync;
@@ -28,7 +29,13 @@ pub use self::{
     scheduler::info::{AtomicCpuId, TaskScheduleInfo},
 };
 pub(crate) use crate::arch::task::{context_switch, TaskContext};
-use crate::{cpu::context::UserContext, prelude::*, trap::in_interrupt_context};
+use crate::{
+    coverage::{self, TaskPcTrace},
+    cpu::context::UserContext,
+    mm::kspace::is_kernel_text,
+    prelude::*,
+    trap::in_interrupt_context,
+};
 
 static POST_SCHEDULE_HANDLER: Once<fn()> = Once::new();
 
@@ -37,6 +44,17 @@ pub fn inject_post_schedule_handler(handler: fn()) {
     POST_SCHEDULE_HANDLER.call_once(|| handler);
 }
 
+/// Terminates the current task without returning to the task function.
+///
+/// The on-stack variables of the current task are leaked, so this should only be
+/// used if the current task cannot continue, e.g., after a fatal CPU exception.
+pub(crate) fn terminate_current() -> ! {
+    let current_task = Task::current().expect("no current task to terminate");
+    coverage::disable_current(&current_task);
+
+    scheduler::exit_current();
+}
+
 /// A task that executes a function to the end.
 ///
 /// Each task is associated with per-task data and an optional user space.
@@ -61,7 +79,13 @@ pub struct Task {
     /// See [`processor::switch_to_task`] for more details.
     switched_to_cpu: AtomicBool,
 
+    /// If the task is sleeping in a [`Waiter`](crate::sync::Waiter).
+    is_sleeping: AtomicBool,
+
     schedule_info: TaskScheduleInfo,
+
+    /// The PC trace enabled for the task. See [`crate::coverage`].
+    pc_trace: TaskPcTrace,
 }
 
 impl Task {
@@ -79,6 +103,52 @@ impl Task {
         &self.ctx
     }
 
+    pub(crate) fn pc_trace(&self) -> &TaskPcTrace {
+        &self.pc_trace
+    }
+
+    /// Returns the address range of the kernel stack.
+    pub(crate) fn kernel_stack_range(&self) -> Range<Vaddr> {
+        let end = self.kstack.end_vaddr();
+        end - KERNEL_STACK_SIZE..end
+    }
+
+    /// Scans the kernel stack of the task for the addresses in the kernel code.
+    ///
+    /// This is for diagnosing a task that sleeps for too long. The stack is
+    /// scanned upwards from the stack pointer saved when the task was switched
+    /// out, so the result is meaningless if the task is running. Since every
+    /// word that looks like a code address is taken, some of the addresses may
+    /// be stale, like the entries marked with `?` in the stack dumps of Linux.
+    ///
+    /// At most `max_addrs` addresses are returned, starting from the innermost
+    /// frame.
+    pub fn scan_kernel_stack(&self, max_addrs: usize) -> Vec<Vaddr> {
+        let stack = self.kernel_stack_range();
+        // SAFETY: The context is only written when the task is switched, and
+        // it is read as plain integers, so a racy read only yields garbage.
+        let ctx = unsafe { self.ctx.get().read_volatile() };
+        let stack_pointer = ctx.stack_pointer().align_up(size_of::<usize>());
+        if !stack.contains(&stack_pointer) {
+            return Vec::new();
+        }
+
+        let mut addrs = Vec::new();
+        for addr in (stack_pointer..stack.end).step_by(size_of::<usize>()) {
+            if addrs.len() >= max_addrs {
+                break;
+            }
+            // SAFETY: The address is on the kernel stack of the task, which is
+            // mapped as long as the task is alive. The value may be changed by
+            // the task concurrently, but it is only reported as a number.
+            let value = unsafe { (addr as *const usize).read_volatile() };
+            if is_kernel_text(value) {
+                addrs.push(value);
+            }
+        }
+        addrs
+    }
+
     /// Sets thread-local storage pointer.
     pub fn set_tls_pointer(&self, tls: usize) {
         let ctx_ptr = self.ctx.get();
@@ -112,6 +182,19 @@ impl Task {
         scheduler::run_new_task(self.clone());
     }
 
+    /// Returns whether the task is sleeping, i.e., waiting to be woken up by a
+    /// [`Waker`](crate::sync::Waker).
+    ///
+    /// The returned value may be outdated as soon as this method returns, so it
+    /// should only be used for statistics and diagnostics.
+    pub fn is_sleeping(&self) -> bool {
+        self.is_sleeping.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_sleeping(&self, is_sleeping: bool) {
+        self.is_sleeping.store(is_sleeping, Ordering::Relaxed);
+    }
+
     /// Returns the task data.
     pub fn data(&self) -> &Box<dyn Any + Send + Sync> {
         &self.data
@@ -138,14 +221,6 @@ impl Task {
         };
         user_ctx.fpu_state().save();
     }
-
-    /// Restores the FPU state for user task.
-    pub fn restore_fpu_state(&self) {
-        let Some(user_ctx) = self.user_ctx.as_ref() else {
-            return;
-        };
-        user_ctx.fpu_state().restore();
-    }
 }
 
 /// Options to create or spawn a new task.
@@ -215,8 +290,6 @@ impl TaskOptions {
             let current_task = Task::current()
                 .expect("no current task, it should have current task in kernel task entry");
 
-            current_task.restore_fpu_state();
-
             // SAFETY: The `func` field will only be accessed by the current task in the task
             // context, so the data won't be accessed concurrently.
             let task_func = unsafe { current_task.func.get() };
@@ -225,6 +298,10 @@ impl TaskOptions {
                 .expect("task function is `None` when trying to run");
             task_func();
 
+            // The trace must not be kept until the task is dropped, or it
+            // cannot be enabled for other tasks in the meantime.
+            coverage::disable_current(&current_task);
+
             // Manually drop all the on-stack variables to prevent memory leakage!
             // This is needed because `scheduler::exit_current()` will never return.
             //
@@ -239,6 +316,8 @@ impl TaskOptions {
         let mut ctx = SyncUnsafeCell::new(TaskContext::default());
         if let Some(user_ctx) = self.user_ctx.as_ref() {
             ctx.get_mut().set_tls_pointer(user_ctx.tls_pointer());
+            #[cfg(target_arch = "x86_64")]
+            ctx.get_mut().set_user_gsbase(user_ctx.gsbase());
         };
         ctx.get_mut()
             .set_instruction_pointer(kernel_task_entry as usize);
@@ -263,6 +342,8 @@ impl TaskOptions {
                 cpu: AtomicCpuId::default(),
             },
             switched_to_cpu: AtomicBool::new(false),
+            is_sleeping: AtomicBool::new(false),
+            pc_trace: TaskPcTrace::default(),
         };
 
         Ok(new_task)
@@ -62,6 +62,7 @@ pub(super) fn switch_to_task(next_task: Arc<Task>) {
     };
 
     before_switching_to(&next_task, &irq_guard);
+    crate::coverage::switch_to(&next_task);
 
     // `before_switching_to` guarantees that from now on, and while the next task is running on the
     // CPU, its context can be used exclusively.
@@ -87,9 +88,7 @@ pub(super) fn switch_to_task(next_task: Arc<Task>) {
     // SAFETY: The task is just switched back, `after_switching_to` hasn't been called yet.
     unsafe { after_switching_to() };
 
-    if let Some(current) = Task::current() {
-        current.restore_fpu_state();
-    }
+    // The FPU state of the current task is restored lazily before returning to the user mode.
 }
 
 fn before_switching_to(next_task: &Task, irq_guard: &DisabledLocalIrqGuard) {
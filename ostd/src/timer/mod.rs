@@ -5,16 +5,25 @@
 pub(crate) mod jiffies;
 
 use alloc::{boxed::Box, vec::Vec};
-use core::cell::RefCell;
+use core::{
+    cell::RefCell,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 pub use jiffies::Jiffies;
 
-use crate::{cpu_local, trap};
+use crate::{
+    cpu::{CpuId, PinCurrentCpu},
+    cpu_local,
+    trap::{self, DisabledLocalIrqGuard},
+};
 
 type InterruptCallback = Box<dyn Fn() + Sync + Send>;
 
 cpu_local! {
     pub(crate) static INTERRUPT_CALLBACKS: RefCell<Vec<InterruptCallback>> = RefCell::new(Vec::new());
+    /// Whether the timer interrupts are stopped on the CPU.
+    pub(crate) static IS_TICK_STOPPED: AtomicBool = AtomicBool::new(false);
 }
 
 /// Register a function that will be executed during the system timer interruption.
@@ -28,3 +37,47 @@ where
         .borrow_mut()
         .push(Box::new(func));
 }
+
+/// Stops the system timer interrupts on the current CPU.
+///
+/// Until [`restart_tick`] is called on the CPU, the functions registered with
+/// [`register_callback`] are not executed on the CPU. This allows a CPU that runs a single task
+/// to run without being disturbed.
+///
+/// The timer of the BSP cannot be stopped, since it updates the [`Jiffies`]. The timer cannot be
+/// stopped either if it is shared by all the CPUs. This function returns whether the timer is
+/// stopped.
+pub fn stop_tick(irq_guard: &DisabledLocalIrqGuard) -> bool {
+    let cpu = irq_guard.current_cpu();
+    if cpu == CpuId::bsp() {
+        return false;
+    }
+
+    let is_stopped = IS_TICK_STOPPED.get_on_cpu(cpu);
+    if is_stopped.load(Ordering::Relaxed) {
+        return true;
+    }
+    if !crate::arch::timer::stop_local_timer() {
+        return false;
+    }
+    is_stopped.store(true, Ordering::Relaxed);
+    true
+}
+
+/// Restarts the system timer interrupts on the current CPU if they are stopped by
+/// [`stop_tick`].
+///
+/// This function returns whether the timer is restarted.
+pub fn restart_tick(irq_guard: &DisabledLocalIrqGuard) -> bool {
+    let is_stopped = IS_TICK_STOPPED.get_on_cpu(irq_guard.current_cpu());
+    if !is_stopped.swap(false, Ordering::Relaxed) {
+        return false;
+    }
+    crate::arch::timer::restart_local_timer();
+    true
+}
+
+/// Returns whether the system timer interrupts are stopped on the CPU.
+pub fn is_tick_stopped(cpu: CpuId) -> bool {
+    IS_TICK_STOPPED.get_on_cpu(cpu).load(Ordering::Relaxed)
+}
@@ -1,9 +1,20 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
 use spin::Once;
 
 use super::{disable_local, irq::process_top_half, DisabledLocalIrqGuard};
-use crate::{cpu_local_cell, task::disable_preempt, trap::TrapFrame};
+use crate::{
+    arch::read_tsc,
+    cpu::CpuId,
+    cpu_local, cpu_local_cell,
+    task::{atomic_mode::is_in_atomic_mode, disable_preempt, terminate_current, Task},
+    trap::TrapFrame,
+};
 
 static BOTTOM_HALF_HANDLER: Once<fn(DisabledLocalIrqGuard) -> DisabledLocalIrqGuard> = Once::new();
 
@@ -42,14 +53,67 @@ fn process_bottom_half() {
     // when the handler returns to prevent race conditions.
     // See <https://github.com/asterinas/asterinas/pull/1623#discussion_r1964709636> for more details.
     let irq_guard = disable_local();
+    let clocks = INTERRUPT_CLOCKS.get_with(&irq_guard);
+    let start = read_tsc();
+    let top_half_start = clocks.top_half.load(Ordering::Relaxed);
+    drop(clocks);
+
     let irq_guard = handler(irq_guard);
 
+    // The top halves that interrupt the bottom half are not counted twice.
+    let clocks = INTERRUPT_CLOCKS.get_with(&irq_guard);
+    let nested_top_half = clocks.top_half.load(Ordering::Relaxed) - top_half_start;
+    let elapsed = read_tsc().saturating_sub(start);
+    clocks
+        .bottom_half
+        .fetch_add(elapsed.saturating_sub(nested_top_half), Ordering::Relaxed);
+    drop(clocks);
+
     // Interrupts should remain disabled when `process_bottom_half` returns,
     // so we simply forget the guard.
     core::mem::forget(irq_guard);
     drop(preempt_guard);
 }
 
+static KERNEL_EXCEPTION_HANDLER: Once<fn(fmt::Arguments<'_>) -> bool> = Once::new();
+
+/// Injects a handler for the fatal CPU exceptions in the kernel.
+///
+/// A fatal CPU exception (e.g., a page fault at an invalid kernel address) is
+/// caused by a bug in the kernel, so the trapped code cannot continue. By
+/// default, OSTD panics on such an exception, and the panic cannot be caught
+/// since the stack cannot be unwound across the trap.
+///
+/// If the exception occurs in a task outside atomic mode, the handler is called
+/// with the description of the exception. If the handler returns `true`, the
+/// current task is terminated without returning to the trapped code, while the
+/// other tasks continue to run. Note that the variables on the stack of the
+/// terminated task are leaked, including the guards of the sleeping locks.
+///
+/// This function can only be registered once. Subsequent calls will do nothing.
+pub fn inject_kernel_exception_handler(handler: fn(fmt::Arguments<'_>) -> bool) {
+    KERNEL_EXCEPTION_HANDLER.call_once(|| handler);
+}
+
+/// Handles a fatal CPU exception in the kernel.
+///
+/// This function terminates the current task if the handler injected by
+/// [`inject_kernel_exception_handler`] allows it. Otherwise, it panics with
+/// the given description.
+#[track_caller]
+pub(crate) fn handle_fatal_kernel_exception(args: fmt::Arguments<'_>) -> ! {
+    if let Some(handler) = KERNEL_EXCEPTION_HANDLER.get()
+        && Task::current().is_some()
+        && !in_interrupt_context()
+        && !is_in_atomic_mode()
+        && handler(args)
+    {
+        terminate_current();
+    }
+
+    panic!("{}", args);
+}
+
 pub(crate) fn call_irq_callback_functions(trap_frame: &TrapFrame, irq_number: usize) {
     // We do not provide support for reentrant interrupt handlers. Otherwise, it's very hard to
     // guarantee the absence of stack overflows.
@@ -63,8 +127,15 @@ pub(crate) fn call_irq_callback_functions(trap_frame: &TrapFrame, irq_number: us
     // bottom half cannot be reentrant for the same reason.
     INTERRUPT_NESTED_LEVEL.add_assign(1);
 
+    let start = read_tsc();
     process_top_half(trap_frame, irq_number);
     crate::arch::interrupts_ack(irq_number);
+    let elapsed = read_tsc().saturating_sub(start);
+    {
+        let irq_guard = disable_local();
+        let clocks = INTERRUPT_CLOCKS.get_with(&irq_guard);
+        clocks.top_half.fetch_add(elapsed, Ordering::Relaxed);
+    }
 
     if INTERRUPT_NESTED_LEVEL.load() == 1 {
         process_bottom_half();
@@ -74,7 +145,23 @@ pub(crate) fn call_irq_callback_functions(trap_frame: &TrapFrame, irq_number: us
 }
 
 cpu_local_cell! {
-    static INTERRUPT_NESTED_LEVEL: u8 = 0;
+    pub(crate) static INTERRUPT_NESTED_LEVEL: u8 = 0;
+}
+
+/// The time that a CPU spends in processing the interrupts.
+struct InterruptClocks {
+    /// The number of the TSC clocks in the top halves.
+    top_half: AtomicU64,
+    /// The number of the TSC clocks in the bottom halves, excluding the
+    /// top halves that interrupt them.
+    bottom_half: AtomicU64,
+}
+
+cpu_local! {
+    static INTERRUPT_CLOCKS: InterruptClocks = InterruptClocks {
+        top_half: AtomicU64::new(0),
+        bottom_half: AtomicU64::new(0),
+    };
 }
 
 /// Returns whether we are in the interrupt context.
@@ -83,3 +170,23 @@ cpu_local_cell! {
 pub fn in_interrupt_context() -> bool {
     INTERRUPT_NESTED_LEVEL.load() != 0
 }
+
+/// Returns the number of the TSC clocks that the CPU spends in processing
+/// the top halves of the interrupts since the system boots.
+pub fn top_half_clocks(cpu: CpuId) -> u64 {
+    INTERRUPT_CLOCKS
+        .get_on_cpu(cpu)
+        .top_half
+        .load(Ordering::Relaxed)
+}
+
+/// Returns the number of the TSC clocks that the CPU spends in processing
+/// the bottom halves of the interrupts since the system boots.
+///
+/// The top halves that interrupt the bottom halves are not included.
+pub fn bottom_half_clocks(cpu: CpuId) -> u64 {
+    INTERRUPT_CLOCKS
+        .get_on_cpu(cpu)
+        .bottom_half
+        .load(Ordering::Relaxed)
+}
@@ -5,6 +5,7 @@ use core::{fmt::Debug, ops::Deref};
 use id_alloc::IdAlloc;
 use spin::Once;
 
+use super::irq_thread::{IrqReturn, IrqThreadHandle, IrqThreadPriority};
 use crate::{
     arch::irq::{self, IrqRemapping, IRQ_NUM_MAX, IRQ_NUM_MIN},
     prelude::*,
@@ -33,6 +34,7 @@ pub type IrqCallbackFunction = dyn Fn(&TrapFrame) + Sync + Send + 'static;
 pub struct IrqLine {
     inner: Arc<InnerHandle>,
     callbacks: Vec<CallbackHandle>,
+    irq_threads: Vec<IrqThreadHandle>,
 }
 
 impl IrqLine {
@@ -61,6 +63,7 @@ impl IrqLine {
         Self {
             inner: Arc::new(inner),
             callbacks: Vec::new(),
+            irq_threads: Vec::new(),
         }
     }
 
@@ -92,6 +95,40 @@ impl IrqLine {
         self.callbacks.push(callback_handle);
     }
 
+    /// Registers a threaded handler that will be invoked when the IRQ is active.
+    ///
+    /// The handler is split into two parts:
+    ///  - `quick_check` is invoked in the interrupt context, just like the callbacks registered
+    ///    with [`Self::on_active`]. It should acknowledge the interrupt in the device and return
+    ///    [`IrqReturn::WakeThread`] if the interrupt needs further handling.
+    ///  - `thread_fn` is invoked in a dedicated IRQ thread with the given priority after the
+    ///    thread is woken up. It runs in the task context, so it can take a long time or sleep.
+    ///
+    /// If the IRQ thread is woken up multiple times before it runs, `thread_fn` may be invoked
+    /// only once. So `thread_fn` should handle all the pending work of the device.
+    ///
+    /// The IRQ thread will exit when this IRQ line is dropped.
+    pub fn on_active_threaded<F, G>(
+        &mut self,
+        quick_check: F,
+        thread_fn: G,
+        priority: IrqThreadPriority,
+    ) where
+        F: Fn(&TrapFrame) -> IrqReturn + Sync + Send + 'static,
+        G: Fn() + Sync + Send + 'static,
+    {
+        let irq_thread = IrqThreadHandle::spawn(thread_fn, priority);
+
+        let wake_thread = irq_thread.waker();
+        self.on_active(move |trap_frame| {
+            if quick_check(trap_frame) == IrqReturn::WakeThread {
+                wake_thread();
+            }
+        });
+
+        self.irq_threads.push(irq_thread);
+    }
+
     /// Checks if there are no registered callbacks.
     pub fn is_empty(&self) -> bool {
         self.callbacks.is_empty()
@@ -111,6 +148,7 @@ impl Clone for IrqLine {
         Self {
             inner: self.inner.clone(),
             callbacks: Vec::new(),
+            irq_threads: Vec::new(),
         }
     }
 }
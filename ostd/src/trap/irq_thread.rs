@@ -0,0 +1,203 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Threaded IRQ handlers.
+//!
+//! A threaded IRQ handler consists of two parts. The quick check runs in the interrupt context
+//! like an ordinary IRQ callback. It acknowledges the interrupt and decides whether the rest of
+//! the work should be done. The threaded handler runs in a dedicated kernel thread, so it can take
+//! a long time or even sleep without blocking the delivery of other interrupts.
+//!
+//! OSTD does not know how to schedule the IRQ threads. The OSTD user should inject a spawner with
+//! [`inject_irq_thread_spawner`] to run the IRQ threads with the given priorities, usually in a
+//! real-time scheduling class. Before that, the threaded handlers can be registered, but they will
+//! not run.
+
+use core::{
+    fmt::Debug,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use spin::Once;
+
+use crate::{
+    prelude::*,
+    sync::{SpinLock, WaitQueue},
+    task::Task,
+};
+
+/// The result of the quick check of a threaded IRQ handler.
+///
+/// See [`IrqLine::on_active_threaded`] for details.
+///
+/// [`IrqLine::on_active_threaded`]: crate::trap::IrqLine::on_active_threaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqReturn {
+    /// The interrupt does not need further handling.
+    Handled,
+    /// The threaded handler should be woken up to handle the interrupt.
+    WakeThread,
+}
+
+/// The priority of an IRQ thread.
+///
+/// The priority ranges from [`Self::MIN`] to [`Self::MAX`], and a larger value means a higher
+/// priority. It is interpreted as a real-time priority by the spawner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct IrqThreadPriority(u8);
+
+impl IrqThreadPriority {
+    /// The lowest priority.
+    pub const MIN: Self = Self(1);
+    /// The highest priority.
+    pub const MAX: Self = Self(99);
+
+    /// Creates a priority.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `prio` is not in the range from [`Self::MIN`] to [`Self::MAX`].
+    pub const fn new(prio: u8) -> Self {
+        assert!(Self::MIN.0 <= prio && prio <= Self::MAX.0);
+        Self(prio)
+    }
+
+    /// Returns the value of the priority.
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl Default for IrqThreadPriority {
+    /// Returns the default priority, which is in the middle of the range as in Linux.
+    fn default() -> Self {
+        Self(50)
+    }
+}
+
+/// The type of the function that spawns IRQ threads.
+pub type IrqThreadSpawner = fn(Box<dyn FnOnce() + Send>, IrqThreadPriority);
+
+static IRQ_THREAD_SPAWNER: Once<IrqThreadSpawner> = Once::new();
+
+/// The IRQ threads that are registered before the spawner is injected.
+static PENDING_IRQ_THREADS: SpinLock<Vec<PendingIrqThread>> = SpinLock::new(Vec::new());
+
+type PendingIrqThread = (Box<dyn FnOnce() + Send>, IrqThreadPriority);
+
+/// Injects a spawner that spawns IRQ threads.
+///
+/// The spawner should spawn a thread with the given priority to run the function to the end.
+///
+/// The IRQ threads registered before the injection are spawned when the spawner is injected.
+pub fn inject_irq_thread_spawner(spawner: IrqThreadSpawner) {
+    let pending_threads = {
+        let mut pending_threads = PENDING_IRQ_THREADS.lock();
+        IRQ_THREAD_SPAWNER.call_once(|| spawner);
+        core::mem::take(&mut *pending_threads)
+    };
+
+    for (thread_fn, priority) in pending_threads {
+        spawner(thread_fn, priority);
+    }
+}
+
+fn spawn_irq_thread(thread_fn: Box<dyn FnOnce() + Send>, priority: IrqThreadPriority) {
+    let spawner = {
+        let mut pending_threads = PENDING_IRQ_THREADS.lock();
+        let Some(spawner) = IRQ_THREAD_SPAWNER.get() else {
+            pending_threads.push((thread_fn, priority));
+            return;
+        };
+        *spawner
+    };
+
+    spawner(thread_fn, priority);
+}
+
+/// The shared state between the quick check and the IRQ thread.
+struct IrqThread {
+    handler: Box<dyn Fn() + Send + Sync>,
+    is_pending: AtomicBool,
+    is_stopped: AtomicBool,
+    wait_queue: WaitQueue,
+}
+
+impl IrqThread {
+    fn run(&self) {
+        loop {
+            self.wait_queue.wait_until(|| {
+                (self.is_pending.load(Ordering::Acquire) || self.is_stopped.load(Ordering::Acquire))
+                    .then_some(())
+            });
+            if self.is_stopped.load(Ordering::Acquire) {
+                return;
+            }
+
+            // Clear the flag before handling, so the interrupts that arrive during the handling
+            // will wake up the thread again.
+            self.is_pending.store(false, Ordering::Relaxed);
+            (self.handler)();
+
+            // Give other threads with the same priority a chance to run.
+            Task::yield_now();
+        }
+    }
+}
+
+/// A handle for an IRQ thread.
+///
+/// When the handle is dropped, the IRQ thread will be stopped automatically.
+pub(super) struct IrqThreadHandle {
+    irq_thread: Arc<IrqThread>,
+}
+
+impl IrqThreadHandle {
+    /// Spawns an IRQ thread that runs `handler` whenever it is woken up.
+    pub(super) fn spawn<F>(handler: F, priority: IrqThreadPriority) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let irq_thread = Arc::new(IrqThread {
+            handler: Box::new(handler),
+            is_pending: AtomicBool::new(false),
+            is_stopped: AtomicBool::new(false),
+            wait_queue: WaitQueue::new(),
+        });
+
+        let thread_fn = {
+            let irq_thread = irq_thread.clone();
+            Box::new(move || irq_thread.run())
+        };
+        spawn_irq_thread(thread_fn, priority);
+
+        Self { irq_thread }
+    }
+
+    /// Returns a waker that wakes up the IRQ thread to run the handler.
+    ///
+    /// The waker can be called in the interrupt context.
+    pub(super) fn waker(&self) -> impl Fn() + Send + Sync + 'static {
+        let irq_thread = self.irq_thread.clone();
+        move || {
+            irq_thread.is_pending.store(true, Ordering::Release);
+            irq_thread.wait_queue.wake_one();
+        }
+    }
+}
+
+impl Debug for IrqThreadHandle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("IrqThreadHandle")
+            .field("is_pending", &self.irq_thread.is_pending)
+            .field("is_stopped", &self.irq_thread.is_stopped)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for IrqThreadHandle {
+    fn drop(&mut self) {
+        // The thread will exit after the running handler, if any, returns.
+        self.irq_thread.is_stopped.store(true, Ordering::Release);
+        self.irq_thread.wait_queue.wake_one();
+    }
+}
@@ -4,9 +4,18 @@
 
 mod handler;
 mod irq;
+mod irq_thread;
 
-pub use handler::{in_interrupt_context, register_bottom_half_handler};
+pub use handler::{
+    bottom_half_clocks, in_interrupt_context, inject_kernel_exception_handler,
+    register_bottom_half_handler, top_half_clocks,
+};
 
-pub(crate) use self::handler::call_irq_callback_functions;
-pub use self::irq::{disable_local, DisabledLocalIrqGuard, IrqCallbackFunction, IrqLine};
+pub(crate) use self::handler::{
+    call_irq_callback_functions, handle_fatal_kernel_exception, INTERRUPT_NESTED_LEVEL,
+};
+pub use self::{
+    irq::{disable_local, DisabledLocalIrqGuard, IrqCallbackFunction, IrqLine},
+    irq_thread::{inject_irq_thread_spawner, IrqReturn, IrqThreadPriority, IrqThreadSpawner},
+};
 pub use crate::arch::trap::TrapFrame;
@@ -0,0 +1,279 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <signal.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define ROOT "/sys/fs/cgroup"
+#define TEST_CGROUP ROOT "/test"
+
+static int write_file(const char *path, const char *contents)
+{
+	ssize_t len;
+	int fd;
+
+	fd = open(path, O_WRONLY);
+	if (fd < 0)
+		return -1;
+	len = write(fd, contents, strlen(contents));
+	close(fd);
+
+	return len < 0 ? -1 : 0;
+}
+
+static int read_file_and_check(const char *path, const char *expected)
+{
+	char buf[256];
+	ssize_t len;
+	int fd;
+
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+	len = read(fd, buf, sizeof(buf));
+	close(fd);
+	if (len < 0)
+		return -1;
+
+	return len == strlen(expected) && memcmp(buf, expected, len) == 0;
+}
+
+FN_SETUP(create_cgroup)
+{
+	CHECK(mkdir(TEST_CGROUP, 0755));
+}
+END_SETUP()
+
+FN_TEST(hierarchy)
+{
+	struct stat st;
+
+	TEST_RES(read_file_and_check(ROOT "/cgroup.controllers",
+				     "cpu memory\n"),
+		 _ret == 1);
+	TEST_ERRNO(stat(ROOT "/cpu.max", &st), ENOENT);
+	TEST_RES(stat(TEST_CGROUP "/cpu.max", &st), S_ISREG(st.st_mode));
+
+	TEST_ERRNO(mkdir(TEST_CGROUP, 0755), EEXIST);
+	TEST_ERRNO(mkdir(ROOT "/cgroup.procs", 0755), EEXIST);
+	TEST_ERRNO(rmdir(ROOT "/nonexistent"), ENOENT);
+
+	TEST_RES(read_file_and_check(TEST_CGROUP "/cgroup.events",
+				     "populated 0\nfrozen 0\n"),
+		 _ret == 1);
+}
+END_TEST()
+
+FN_TEST(subtree_control)
+{
+	TEST_RES(read_file_and_check(TEST_CGROUP "/cgroup.controllers", "\n"),
+		 _ret == 1);
+
+	TEST_SUCC(write_file(ROOT "/cgroup.subtree_control", "+cpu +memory"));
+	TEST_RES(read_file_and_check(TEST_CGROUP "/cgroup.controllers",
+				     "cpu memory\n"),
+		 _ret == 1);
+
+	TEST_SUCC(write_file(ROOT "/cgroup.subtree_control", "-memory"));
+	TEST_RES(read_file_and_check(ROOT "/cgroup.subtree_control", "cpu\n"),
+		 _ret == 1);
+
+	TEST_ERRNO(write_file(ROOT "/cgroup.subtree_control", "cpu"), EINVAL);
+	TEST_ERRNO(write_file(ROOT "/cgroup.subtree_control", "+io"), EINVAL);
+	TEST_ERRNO(write_file(TEST_CGROUP "/cgroup.subtree_control",
+			      "+memory"),
+		   ENOENT);
+
+	TEST_SUCC(write_file(ROOT "/cgroup.subtree_control", "+memory"));
+}
+END_TEST()
+
+FN_TEST(cpu_max)
+{
+	TEST_RES(read_file_and_check(TEST_CGROUP "/cpu.max", "max 100000\n"),
+		 _ret == 1);
+
+	TEST_SUCC(write_file(TEST_CGROUP "/cpu.max", "50000 200000"));
+	TEST_RES(read_file_and_check(TEST_CGROUP "/cpu.max", "50000 200000\n"),
+		 _ret == 1);
+
+	TEST_SUCC(write_file(TEST_CGROUP "/cpu.max", "max"));
+	TEST_RES(read_file_and_check(TEST_CGROUP "/cpu.max", "max 200000\n"),
+		 _ret == 1);
+
+	TEST_ERRNO(write_file(TEST_CGROUP "/cpu.max", "500 100000"), EINVAL);
+	TEST_ERRNO(write_file(TEST_CGROUP "/cpu.max", "max 100"), EINVAL);
+	TEST_ERRNO(write_file(TEST_CGROUP "/cpu.max", "abc"), EINVAL);
+
+	TEST_SUCC(write_file(TEST_CGROUP "/cpu.max", "max 100000"));
+}
+END_TEST()
+
+FN_TEST(memory_max)
+{
+	TEST_RES(read_file_and_check(TEST_CGROUP "/memory.max", "max\n"),
+		 _ret == 1);
+
+	TEST_SUCC(write_file(TEST_CGROUP "/memory.max", "1M"));
+	TEST_RES(read_file_and_check(TEST_CGROUP "/memory.max", "1048576\n"),
+		 _ret == 1);
+
+	// The limit is rounded down to the page size.
+	TEST_SUCC(write_file(TEST_CGROUP "/memory.max", "8193"));
+	TEST_RES(read_file_and_check(TEST_CGROUP "/memory.max", "8192\n"),
+		 _ret == 1);
+
+	TEST_ERRNO(write_file(TEST_CGROUP "/memory.max", "1X"), EINVAL);
+
+	TEST_SUCC(write_file(TEST_CGROUP "/memory.max", "max"));
+	TEST_RES(read_file_and_check(TEST_CGROUP "/memory.max", "max\n"),
+		 _ret == 1);
+}
+END_TEST()
+
+static int pipe_fds[2];
+
+static pid_t spawn_in_test_cgroup(size_t alloc_size)
+{
+	char *buf;
+	pid_t pid;
+	size_t i;
+	char c;
+
+	if (pipe(pipe_fds) < 0)
+		return -1;
+
+	pid = fork();
+	if (pid != 0) {
+		close(pipe_fds[0]);
+		return pid;
+	}
+
+	close(pipe_fds[1]);
+	if (write_file(TEST_CGROUP "/cgroup.procs", "0") < 0)
+		_exit(1);
+	if (read_file_and_check("/proc/self/cgroup", "0::/test\n") != 1)
+		_exit(1);
+
+	buf = mmap(NULL, alloc_size, PROT_READ | PROT_WRITE,
+		   MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+	if (buf == MAP_FAILED)
+		_exit(1);
+	for (i = 0; i < alloc_size; i += 4096)
+		buf[i] = 1;
+
+	// Wait until the write end of the pipe is closed.
+	if (read(pipe_fds[0], &c, 1) != 0)
+		_exit(1);
+	_exit(0);
+}
+
+static int wait_for_exit(pid_t pid, int *status)
+{
+	close(pipe_fds[1]);
+	return waitpid(pid, status, 0);
+}
+
+FN_TEST(procs)
+{
+	char buf[32];
+	int status;
+	pid_t pid;
+
+	pid = TEST_SUCC(spawn_in_test_cgroup(4096));
+	while (read_file_and_check(TEST_CGROUP "/cgroup.events",
+				   "populated 1\nfrozen 0\n") == 0)
+		usleep(1000);
+
+	snprintf(buf, sizeof(buf), "%d\n", pid);
+	TEST_RES(read_file_and_check(TEST_CGROUP "/cgroup.procs", buf),
+		 _ret == 1);
+	TEST_RES(read_file_and_check("/proc/self/cgroup", "0::/\n"),
+		 _ret == 1);
+	TEST_ERRNO(rmdir(TEST_CGROUP), EBUSY);
+
+	TEST_RES(wait_for_exit(pid, &status),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+	TEST_RES(read_file_and_check(TEST_CGROUP "/cgroup.procs", ""),
+		 _ret == 1);
+	TEST_RES(read_file_and_check(TEST_CGROUP "/memory.current", "0\n"),
+		 _ret == 1);
+
+	TEST_ERRNO(write_file(TEST_CGROUP "/cgroup.procs", "-1"), EINVAL);
+	TEST_ERRNO(write_file(TEST_CGROUP "/cgroup.procs", "1000000"), ESRCH);
+}
+END_TEST()
+
+FN_TEST(freeze)
+{
+	int status;
+	pid_t pid;
+
+	TEST_ERRNO(write_file(TEST_CGROUP "/cgroup.freeze", "2"), EINVAL);
+	TEST_RES(read_file_and_check(TEST_CGROUP "/cgroup.freeze", "0\n"),
+		 _ret == 1);
+
+	// The process is frozen while it is blocked in `read`.
+	pid = TEST_SUCC(spawn_in_test_cgroup(4096));
+	while (read_file_and_check(TEST_CGROUP "/cgroup.events",
+				   "populated 1\nfrozen 0\n") == 0)
+		usleep(1000);
+	TEST_SUCC(write_file(TEST_CGROUP "/cgroup.freeze", "1"));
+	TEST_RES(read_file_and_check(TEST_CGROUP "/cgroup.freeze", "1\n"),
+		 _ret == 1);
+	while (read_file_and_check(TEST_CGROUP "/cgroup.events",
+				   "populated 1\nfrozen 1\n") == 0)
+		usleep(1000);
+
+	// Unlike `SIGSTOP`, freezing is not reported to the parent.
+	TEST_RES(waitpid(pid, &status, WNOHANG | WUNTRACED), _ret == 0);
+
+	// The interrupted `read` is restarted after the process is thawed.
+	TEST_SUCC(write_file(TEST_CGROUP "/cgroup.freeze", "0"));
+	TEST_RES(read_file_and_check(TEST_CGROUP "/cgroup.events",
+				     "populated 1\nfrozen 0\n"),
+		 _ret == 1);
+	TEST_RES(wait_for_exit(pid, &status),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+
+	// A frozen process can still be killed.
+	pid = TEST_SUCC(spawn_in_test_cgroup(4096));
+	TEST_SUCC(write_file(TEST_CGROUP "/cgroup.freeze", "1"));
+	TEST_SUCC(kill(pid, SIGKILL));
+	TEST_RES(wait_for_exit(pid, &status),
+		 _ret == pid && WIFSIGNALED(status) &&
+			 WTERMSIG(status) == SIGKILL);
+	TEST_SUCC(write_file(TEST_CGROUP "/cgroup.freeze", "0"));
+}
+END_TEST()
+
+FN_TEST(memory_limit)
+{
+	int status;
+	pid_t pid;
+
+	TEST_SUCC(write_file(TEST_CGROUP "/memory.max", "4M"));
+
+	// The process is killed if the memory limit is hit.
+	pid = TEST_SUCC(spawn_in_test_cgroup(16 * 1024 * 1024));
+	TEST_RES(wait_for_exit(pid, &status),
+		 _ret == pid && WIFSIGNALED(status) &&
+			 WTERMSIG(status) == SIGKILL);
+
+	TEST_SUCC(write_file(TEST_CGROUP "/memory.max", "max"));
+}
+END_TEST()
+
+FN_SETUP(remove_cgroup)
+{
+	CHECK(rmdir(TEST_CGROUP));
+}
+END_SETUP()
@@ -0,0 +1,77 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <linux/sched.h>
+#include <signal.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define CLONE_ARGS_SIZE_VER0 64
+
+static struct {
+	struct clone_args args;
+	char extra[8];
+} buf;
+
+static pid_t sys_clone3(void *args, size_t size)
+{
+	return syscall(SYS_clone3, args, size);
+}
+
+// Creates a child process with the size of `struct clone_args` and waits for it.
+static int clone_and_wait(size_t size)
+{
+	pid_t pid;
+	int status;
+
+	pid = sys_clone3(&buf, size);
+	if (pid < 0)
+		return pid;
+	if (pid == 0)
+		_exit(0);
+
+	if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status))
+		return -1;
+	return 0;
+}
+
+FN_SETUP(init)
+{
+	memset(&buf, 0, sizeof(buf));
+	buf.args.exit_signal = SIGCHLD;
+}
+END_SETUP()
+
+FN_TEST(valid_sizes)
+{
+	TEST_SUCC(clone_and_wait(CLONE_ARGS_SIZE_VER0));
+	TEST_SUCC(clone_and_wait(sizeof(buf.args)));
+
+	// The extra bytes are zeros.
+	TEST_SUCC(clone_and_wait(sizeof(buf)));
+}
+END_TEST()
+
+FN_TEST(invalid_sizes)
+{
+	TEST_ERRNO(sys_clone3(&buf, CLONE_ARGS_SIZE_VER0 - 8), EINVAL);
+	TEST_ERRNO(sys_clone3(&buf, 4096 + 8), E2BIG);
+
+	// The extra bytes are not zeros.
+	buf.extra[7] = 1;
+	TEST_ERRNO(sys_clone3(&buf, sizeof(buf)), E2BIG);
+	buf.extra[7] = 0;
+}
+END_TEST()
+
+FN_TEST(invalid_pointers)
+{
+	TEST_ERRNO(sys_clone3(NULL, sizeof(buf.args)), EFAULT);
+	TEST_ERRNO(sys_clone3((void *)1, sizeof(buf.args)), EFAULT);
+}
+END_TEST()
@@ -0,0 +1,92 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <sched.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static char child_stack[4096];
+#define CHILD_STACK_TOP \
+	(child_stack + sizeof(child_stack) / sizeof(child_stack[0]))
+
+static int cwd_is(const char *path)
+{
+	char cwd[256];
+
+	return getcwd(cwd, sizeof(cwd)) != NULL && strcmp(cwd, path) == 0;
+}
+
+static int child_chdir(void *arg)
+{
+	CHECK(chdir("/tmp"));
+	umask(0077);
+
+	_exit(0);
+}
+
+static int child_unshare_and_chdir(void *arg)
+{
+	CHECK(unshare(CLONE_FS));
+	CHECK(chdir("/tmp"));
+	umask(0077);
+
+	_exit(0);
+}
+
+static int run_child(int (*fn)(void *), int flags)
+{
+	pid_t pid;
+	int status;
+
+	pid = CHECK(clone(fn, CHILD_STACK_TOP, flags | SIGCHLD, NULL));
+	CHECK_WITH(wait(&status), _ret == pid);
+
+	return WIFEXITED(status) && WEXITSTATUS(status) == 0;
+}
+
+FN_SETUP(cwd_and_umask)
+{
+	CHECK(chdir("/"));
+	umask(0022);
+}
+END_SETUP()
+
+FN_TEST(clone_without_fs)
+{
+	TEST_RES(run_child(&child_chdir, 0), _ret);
+
+	TEST_RES(cwd_is("/"), _ret);
+	TEST_RES(umask(0022), _ret == 0022);
+}
+END_TEST()
+
+FN_TEST(clone_with_fs)
+{
+	TEST_RES(run_child(&child_chdir, CLONE_FS), _ret);
+
+	TEST_RES(cwd_is("/tmp"), _ret);
+	TEST_RES(umask(0022), _ret == 0077);
+
+	TEST_SUCC(chdir("/"));
+}
+END_TEST()
+
+FN_TEST(clone_with_fs_and_unshare)
+{
+	TEST_RES(run_child(&child_unshare_and_chdir, CLONE_FS), _ret);
+
+	TEST_RES(cwd_is("/"), _ret);
+	TEST_RES(umask(0022), _ret == 0022);
+}
+END_TEST()
+
+FN_TEST(unshare_invalid_flags)
+{
+	TEST_ERRNO(unshare(CLONE_SETTLS), EINVAL);
+}
+END_TEST()
@@ -0,0 +1,536 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+#include <fcntl.h>
+#include <linux/dm-ioctl.h>
+#include <linux/fs.h>
+#include <linux/keyctl.h>
+#include <stdint.h>
+#include <sys/ioctl.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/sysmacros.h>
+#include <unistd.h>
+
+#define DISK_PATH "/dev/vda"
+#define CONTROL_PATH "/dev/mapper/control"
+
+#define SECTOR_SIZE 512
+
+// The partitions of the disk, see `write_mbr`.
+#define PART_SECTORS 8192
+#define PART1_START 2048
+#define PART2_START (PART1_START + PART_SECTORS)
+
+struct target {
+	uint64_t start;
+	uint64_t len;
+	const char *type;
+	const char *params;
+};
+
+static int disk_fd;
+static int control_fd;
+static unsigned char sector[SECTOR_SIZE];
+static char part2_devno[32];
+
+static union {
+	struct dm_ioctl hdr;
+	char data[4096];
+} req;
+
+static struct dm_ioctl *init_req(const char *name)
+{
+	memset(&req, 0, sizeof(req));
+	req.hdr.version[0] = DM_VERSION_MAJOR;
+	req.hdr.data_size = sizeof(req);
+	req.hdr.data_start = sizeof(req.hdr);
+	strncpy(req.hdr.name, name, sizeof(req.hdr.name) - 1);
+	return &req.hdr;
+}
+
+static int dm_ioctl(unsigned long cmd, const char *name)
+{
+	init_req(name);
+	return ioctl(control_fd, cmd, &req);
+}
+
+static int load_table(const char *name, const struct target *targets,
+		      int nr_targets)
+{
+	struct dm_ioctl *hdr = init_req(name);
+	size_t offset = hdr->data_start;
+
+	for (int i = 0; i < nr_targets; i++) {
+		struct dm_target_spec *spec = (void *)req.data + offset;
+		size_t len = sizeof(*spec) + strlen(targets[i].params) + 1;
+
+		len = (len + 7) & ~7;
+		spec->sector_start = targets[i].start;
+		spec->length = targets[i].len;
+		spec->next = len;
+		strcpy(spec->target_type, targets[i].type);
+		strcpy((char *)(spec + 1), targets[i].params);
+		offset += len;
+	}
+	hdr->target_count = nr_targets;
+
+	return ioctl(control_fd, DM_TABLE_LOAD, &req);
+}
+
+static int create_device(const char *name, const struct target *targets,
+			 int nr_targets)
+{
+	if (dm_ioctl(DM_DEV_CREATE, name) < 0)
+		return -1;
+	if (load_table(name, targets, nr_targets) < 0 ||
+	    dm_ioctl(DM_DEV_SUSPEND, name) < 0) {
+		dm_ioctl(DM_DEV_REMOVE, name);
+		return -1;
+	}
+	return 0;
+}
+
+static uint64_t device_size(const char *path)
+{
+	uint64_t size = 0;
+	int fd = open(path, O_RDONLY);
+
+	if (fd < 0)
+		return -1;
+	if (ioctl(fd, BLKGETSIZE64, &size) < 0)
+		size = -1;
+	close(fd);
+	return size;
+}
+
+// Reads the first byte of the sector on the disk.
+static int disk_byte(uint64_t sid)
+{
+	unsigned char byte;
+
+	if (pread(disk_fd, &byte, 1, sid * SECTOR_SIZE) != 1)
+		return -1;
+	return byte;
+}
+
+FN_SETUP(open)
+{
+	disk_fd = CHECK(open(DISK_PATH, O_RDWR));
+	control_fd = CHECK(open(CONTROL_PATH, O_RDWR));
+}
+END_SETUP()
+
+FN_TEST(version)
+{
+	TEST_RES(dm_ioctl(DM_VERSION, ""),
+		 req.hdr.version[0] == DM_VERSION_MAJOR);
+
+	init_req("")->version[0] = DM_VERSION_MAJOR + 1;
+	TEST_ERRNO(ioctl(control_fd, DM_VERSION, &req), EINVAL);
+}
+END_TEST()
+
+// The layout of the MBR:
+// - 1: the primary partition of the sectors [2048, 10240);
+// - 2: the primary partition of the sectors [10240, 18432).
+FN_SETUP(write_mbr)
+{
+	struct stat stat_buf;
+
+	for (int i = 0; i < 2; i++) {
+		unsigned char *entry = sector + 446 + i * 16;
+		uint32_t start = i == 0 ? PART1_START : PART2_START;
+
+		entry[4] = 0x83;
+		memcpy(entry + 8, &start, 4);
+		memcpy(entry + 12, &(uint32_t){ PART_SECTORS }, 4);
+	}
+	sector[510] = 0x55;
+	sector[511] = 0xaa;
+	CHECK_WITH(pwrite(disk_fd, sector, SECTOR_SIZE, 0),
+		   _ret == SECTOR_SIZE);
+	CHECK(ioctl(disk_fd, BLKRRPART));
+
+	CHECK(stat("/dev/vda2", &stat_buf));
+	snprintf(part2_devno, sizeof(part2_devno), "%u:%u",
+		 major(stat_buf.st_rdev), minor(stat_buf.st_rdev));
+}
+END_SETUP()
+
+FN_TEST(linear)
+{
+	const struct target targets[] = {
+		{ 0, PART_SECTORS, "linear", "/dev/vda1 0" },
+		{ PART_SECTORS, PART_SECTORS, "linear", "vda2 0" },
+	};
+	char path[32], buf[16];
+	struct stat stat_buf;
+	int fd;
+
+	TEST_SUCC(create_device("concat", targets, 2));
+	TEST_RES(device_size("/dev/mapper/concat"),
+		 _ret == 2 * PART_SECTORS * SECTOR_SIZE);
+
+	TEST_RES(dm_ioctl(DM_DEV_STATUS, "concat"),
+		 req.hdr.target_count == 2 &&
+			 (req.hdr.flags & DM_ACTIVE_PRESENT_FLAG) &&
+			 !(req.hdr.flags & DM_INACTIVE_PRESENT_FLAG));
+	snprintf(path, sizeof(path), "/dev/dm-%u", minor(req.hdr.dev));
+	TEST_RES(stat(path, &stat_buf),
+		 S_ISBLK(stat_buf.st_mode) && stat_buf.st_rdev == req.hdr.dev);
+
+	// The write crosses the boundary of the two partitions.
+	fd = TEST_SUCC(open("/dev/mapper/concat", O_RDWR));
+	TEST_RES(pwrite(fd, "hello, world", 12, PART_SECTORS * SECTOR_SIZE - 5),
+		 _ret == 12);
+	TEST_RES(pread(disk_fd, buf, 5, PART2_START * SECTOR_SIZE - 5),
+		 _ret == 5 && memcmp(buf, "hello", 5) == 0);
+	TEST_RES(pread(disk_fd, buf, 7, PART2_START * SECTOR_SIZE),
+		 _ret == 7 && memcmp(buf, ", world", 7) == 0);
+	TEST_RES(pread(fd, buf, 12, PART_SECTORS * SECTOR_SIZE - 5),
+		 _ret == 12 && memcmp(buf, "hello, world", 12) == 0);
+	TEST_SUCC(fsync(fd));
+
+	// The device cannot be removed while it is opened.
+	TEST_ERRNO(dm_ioctl(DM_DEV_REMOVE, "concat"), EBUSY);
+	TEST_SUCC(close(fd));
+
+	// The partitions cannot be removed while they are used by the device.
+	TEST_ERRNO(ioctl(disk_fd, BLKRRPART), EBUSY);
+
+	TEST_SUCC(dm_ioctl(DM_DEV_REMOVE, "concat"));
+	TEST_ERRNO(access("/dev/mapper/concat", F_OK), ENOENT);
+	TEST_ERRNO(access(path, F_OK), ENOENT);
+	TEST_ERRNO(dm_ioctl(DM_DEV_REMOVE, "concat"), ENXIO);
+}
+END_TEST()
+
+FN_TEST(striped)
+{
+	char params[64];
+	unsigned char buf[32 * SECTOR_SIZE];
+	int fd, mismatches = 0;
+
+	// The chunks of eight sectors are placed on the two partitions alternately.
+	snprintf(params, sizeof(params), "2 8 /dev/vda1 0 %s 0", part2_devno);
+	const struct target targets[] = {
+		{ 0, 2 * PART_SECTORS, "striped", params },
+	};
+
+	TEST_SUCC(create_device("stripe", targets, 1));
+	TEST_RES(device_size("/dev/mapper/stripe"),
+		 _ret == 2 * PART_SECTORS * SECTOR_SIZE);
+
+	// Each sector starts with its sector number on the device.
+	for (int i = 0; i < 32; i++)
+		buf[i * SECTOR_SIZE] = 4 + i;
+	fd = TEST_SUCC(open("/dev/mapper/stripe", O_RDWR));
+	TEST_RES(pwrite(fd, buf, sizeof(buf), 4 * SECTOR_SIZE),
+		 _ret == sizeof(buf));
+	TEST_SUCC(close(fd));
+
+	for (int sid = 4; sid < 36; sid++) {
+		int chunk = sid / 8;
+		uint64_t start = chunk % 2 == 0 ? PART1_START : PART2_START;
+
+		if (disk_byte(start + chunk / 2 * 8 + sid % 8) != sid)
+			mismatches++;
+	}
+	TEST_RES(mismatches, _ret == 0);
+
+	TEST_SUCC(dm_ioctl(DM_DEV_REMOVE, "stripe"));
+}
+END_TEST()
+
+FN_TEST(table_swap)
+{
+	const struct target vda1[] = {
+		{ 0, PART_SECTORS, "linear", "/dev/vda1 0" },
+	};
+	const struct target vda2[] = {
+		{ 0, PART_SECTORS, "linear", "/dev/vda2 0" },
+	};
+	char buf[4];
+	int fd;
+
+	TEST_SUCC(pwrite(disk_fd, "one", 4, PART1_START * SECTOR_SIZE));
+	TEST_SUCC(pwrite(disk_fd, "two", 4, PART2_START * SECTOR_SIZE));
+
+	TEST_SUCC(create_device("swap", vda1, 1));
+	fd = TEST_SUCC(open("/dev/mapper/swap", O_RDONLY));
+
+	// The loaded table is inactive until the device is resumed.
+	TEST_RES(load_table("swap", vda2, 1),
+		 req.hdr.flags & DM_INACTIVE_PRESENT_FLAG);
+	TEST_RES(pread(fd, buf, 4, 0), _ret == 4 && strcmp(buf, "one") == 0);
+	TEST_SUCC(dm_ioctl(DM_DEV_SUSPEND, "swap"));
+	TEST_RES(pread(fd, buf, 4, 0), _ret == 4 && strcmp(buf, "two") == 0);
+
+	// The inactive table can be cleared.
+	TEST_SUCC(load_table("swap", vda1, 1));
+	TEST_RES(dm_ioctl(DM_TABLE_CLEAR, "swap"),
+		 !(req.hdr.flags & DM_INACTIVE_PRESENT_FLAG));
+	TEST_SUCC(dm_ioctl(DM_DEV_SUSPEND, "swap"));
+	TEST_RES(pread(fd, buf, 4, 0), _ret == 4 && strcmp(buf, "two") == 0);
+
+	TEST_SUCC(close(fd));
+	TEST_SUCC(dm_ioctl(DM_DEV_REMOVE, "swap"));
+}
+END_TEST()
+
+static long add_key(const char *type, const char *description,
+		    const void *payload, size_t len, int keyring)
+{
+	return syscall(SYS_add_key, type, description, payload, len, keyring);
+}
+
+FN_TEST(keyring)
+{
+	long serial;
+
+	serial = TEST_SUCC(add_key("user", "dm:test", "old", 3,
+				   KEY_SPEC_SESSION_KEYRING));
+	// Adding the key again updates its payload and keeps its serial number.
+	TEST_RES(add_key("user", "dm:test", "new", 3, KEY_SPEC_PROCESS_KEYRING),
+		 _ret == serial);
+
+	TEST_ERRNO(add_key("unknown", "dm:test", "key", 3,
+			   KEY_SPEC_SESSION_KEYRING),
+		   ENODEV);
+	TEST_ERRNO(add_key("logon", "test", "key", 3, KEY_SPEC_SESSION_KEYRING),
+		   EINVAL);
+	TEST_ERRNO(add_key("user", "dm:test", "", 0, KEY_SPEC_SESSION_KEYRING),
+		   EINVAL);
+	TEST_ERRNO(add_key("user", "dm:test", "key", 3, 1), ENOKEY);
+}
+END_TEST()
+
+// The test vector 4 of IEEE 1619, whose data unit is the sector 0.
+FN_TEST(crypt_vector)
+{
+	const struct target targets[] = {
+		{ 0, PART_SECTORS, "crypt",
+		  "aes-xts-plain64 "
+		  "27182818284590452353602874713526"
+		  "31415926535897932384626433832795 0 /dev/vda1 0" },
+	};
+	const unsigned char expected[16] = {
+		0x27, 0xa7, 0x47, 0x9b, 0xef, 0xa1, 0xd4, 0x76,
+		0x48, 0x9f, 0x30, 0x8c, 0xd4, 0xcf, 0xa6, 0xe2,
+	};
+	unsigned char buf[SECTOR_SIZE];
+	int fd;
+
+	for (int i = 0; i < SECTOR_SIZE; i++)
+		sector[i] = i;
+
+	TEST_SUCC(create_device("vector", targets, 1));
+	fd = TEST_SUCC(open("/dev/mapper/vector", O_RDWR));
+	TEST_RES(pwrite(fd, sector, SECTOR_SIZE, 0), _ret == SECTOR_SIZE);
+	TEST_SUCC(fsync(fd));
+
+	TEST_RES(pread(disk_fd, buf, SECTOR_SIZE, PART1_START * SECTOR_SIZE),
+		 _ret == SECTOR_SIZE && memcmp(buf, expected, 16) == 0);
+	TEST_RES(pread(fd, buf, SECTOR_SIZE, 0),
+		 _ret == SECTOR_SIZE && memcmp(buf, sector, SECTOR_SIZE) == 0);
+
+	TEST_SUCC(close(fd));
+	TEST_SUCC(dm_ioctl(DM_DEV_REMOVE, "vector"));
+}
+END_TEST()
+
+FN_TEST(crypt_keyring)
+{
+	const struct target targets[] = {
+		{ 0, PART_SECTORS, "crypt",
+		  "aes-xts-plain64 :64:logon:dm:crypt 16 /dev/vda2 0" },
+	};
+	const struct target wrong_size[] = {
+		{ 0, PART_SECTORS, "crypt",
+		  "aes-xts-plain64 :32:logon:dm:crypt 0 /dev/vda2 0" },
+	};
+	const struct target no_key[] = {
+		{ 0, PART_SECTORS, "crypt",
+		  "aes-xts-plain64 :64:logon:dm:none 0 /dev/vda2 0" },
+	};
+	const struct target bad_cipher[] = {
+		{ 0, PART_SECTORS, "crypt",
+		  "aes-cbc-essiv:sha256 :64:logon:dm:crypt 0 /dev/vda2 0" },
+	};
+	unsigned char key[64], buf[4 * SECTOR_SIZE], raw[sizeof(buf)];
+	int fd;
+
+	for (int i = 0; i < sizeof(key); i++)
+		key[i] = i * 7 + 1;
+	for (int i = 0; i < sizeof(buf); i++)
+		buf[i] = "confidential"[i % 12];
+	TEST_SUCC(add_key("logon", "dm:crypt", key, sizeof(key),
+			  KEY_SPEC_USER_KEYRING));
+
+	TEST_SUCC(dm_ioctl(DM_DEV_CREATE, "crypt"));
+	TEST_ERRNO(load_table("crypt", wrong_size, 1), EINVAL);
+	TEST_ERRNO(load_table("crypt", no_key, 1), ENOKEY);
+	TEST_ERRNO(load_table("crypt", bad_cipher, 1), EINVAL);
+	TEST_SUCC(load_table("crypt", targets, 1));
+	TEST_SUCC(dm_ioctl(DM_DEV_SUSPEND, "crypt"));
+
+	// The data are encrypted on the disk and decrypted transparently.
+	fd = TEST_SUCC(open("/dev/mapper/crypt", O_RDWR));
+	TEST_RES(pwrite(fd, buf, sizeof(buf), 8 * SECTOR_SIZE),
+		 _ret == sizeof(buf));
+	TEST_SUCC(fsync(fd));
+	TEST_RES(pread(disk_fd, raw, sizeof(raw),
+		       (PART2_START + 8) * SECTOR_SIZE),
+		 _ret == sizeof(raw) && memcmp(raw, buf, sizeof(buf)) != 0);
+	memset(raw, 0, sizeof(raw));
+	TEST_RES(pread(fd, raw, sizeof(raw), 8 * SECTOR_SIZE),
+		 _ret == sizeof(raw) && memcmp(raw, buf, sizeof(buf)) == 0);
+	TEST_SUCC(close(fd));
+
+	TEST_SUCC(dm_ioctl(DM_DEV_REMOVE, "crypt"));
+}
+END_TEST()
+
+static void parse_hex(const char *hex, unsigned char *bytes)
+{
+	for (size_t i = 0; hex[2 * i] != '\0'; i++)
+		sscanf(hex + 2 * i, "%2hhx", &bytes[i]);
+}
+
+// The data blocks are filled with 'a', 'b', 'c', and 'd', respectively. The
+// hash tree has one level, whose hash block contains the digests of the data
+// blocks.
+#define VERITY_BLOCK_SIZE 4096
+#define VERITY_PARAMS(root)                                        \
+	"1 /dev/vda1 /dev/vda2 4096 4096 4 0 sha256 " root " -"
+#define VERITY_ROOT \
+	"5637a317b773c4f08a8f9f3c57301a0043c3541af622ca4c8a463352e3aab761"
+#define VERITY_DIGESTS                                                     \
+	"c93eee2d0db02f10acc7460d9576e122dcf8cd53c4bf8dfcae1b3e74ebcfff5a" \
+	"5389688abf55bc46639385085bfaf1fda3552f63303e4d4a55d664d0f515d6ac" \
+	"3abc94a93a42d0eee5c8dda0315f9f1343e2ba36b552ab512c435fd4989c1ac6" \
+	"ef94c126bfb6793c3b46596f7acce4a98382cac6de2f3a2a2fe24aa64710c534"
+
+FN_TEST(verity)
+{
+	const struct target targets[] = {
+		{ 0, 32, "verity", VERITY_PARAMS(VERITY_ROOT) },
+	};
+	const struct target wrong_root[] = {
+		{ 0, 32, "verity", VERITY_PARAMS(
+			  "0037a317b773c4f08a8f9f3c57301a0043c3541af622ca4c8a463352e3aab761") },
+	};
+	const struct target bad_algorithm[] = {
+		{ 0, 32, "verity",
+		  "1 /dev/vda1 /dev/vda2 4096 4096 4 0 md5 "
+		  "d41d8cd98f00b204e9800998ecf8427e -" },
+	};
+	const struct target too_large[] = {
+		{ 0, 40, "verity", VERITY_PARAMS(VERITY_ROOT) },
+	};
+	static unsigned char block[VERITY_BLOCK_SIZE], buf[VERITY_BLOCK_SIZE];
+	int fd;
+
+	for (int i = 0; i < 4; i++) {
+		memset(block, 'a' + i, sizeof(block));
+		TEST_RES(pwrite(disk_fd, block, sizeof(block),
+				PART1_START * SECTOR_SIZE + i * sizeof(block)),
+			 _ret == sizeof(block));
+	}
+	memset(block, 0, sizeof(block));
+	parse_hex(VERITY_DIGESTS, block);
+	TEST_RES(pwrite(disk_fd, block, sizeof(block), PART2_START * SECTOR_SIZE),
+		 _ret == sizeof(block));
+	TEST_SUCC(fsync(disk_fd));
+
+	TEST_SUCC(dm_ioctl(DM_DEV_CREATE, "verity"));
+	TEST_ERRNO(load_table("verity", bad_algorithm, 1), EINVAL);
+	TEST_ERRNO(load_table("verity", too_large, 1), EINVAL);
+	TEST_SUCC(load_table("verity", targets, 1));
+	TEST_SUCC(dm_ioctl(DM_DEV_SUSPEND, "verity"));
+
+	// The blocks are verified even if they are read partially.
+	fd = TEST_SUCC(open("/dev/mapper/verity", O_RDWR));
+	TEST_RES(pread(fd, buf, sizeof(buf), 3 * sizeof(buf)),
+		 _ret == sizeof(buf) && buf[0] == 'd' &&
+			 buf[sizeof(buf) - 1] == 'd');
+	TEST_RES(pread(fd, buf, SECTOR_SIZE, 9 * SECTOR_SIZE),
+		 _ret == SECTOR_SIZE && buf[0] == 'b');
+	TEST_ERRNO(pwrite(fd, buf, SECTOR_SIZE, 0), EIO);
+
+	// The corrupted block cannot be read, but the other blocks can.
+	TEST_RES(pwrite(disk_fd, "x", 1, (PART1_START + 17) * SECTOR_SIZE),
+		 _ret == 1);
+	TEST_SUCC(fsync(disk_fd));
+	TEST_ERRNO(pread(fd, buf, SECTOR_SIZE, 16 * SECTOR_SIZE), EIO);
+	TEST_RES(pread(fd, buf, SECTOR_SIZE, 0),
+		 _ret == SECTOR_SIZE && buf[0] == 'a');
+	TEST_SUCC(close(fd));
+
+	// Nothing can be read if the root digest mismatches.
+	TEST_SUCC(load_table("verity", wrong_root, 1));
+	TEST_SUCC(dm_ioctl(DM_DEV_SUSPEND, "verity"));
+	fd = TEST_SUCC(open("/dev/mapper/verity", O_RDONLY));
+	TEST_ERRNO(pread(fd, buf, SECTOR_SIZE, 0), EIO);
+	TEST_SUCC(close(fd));
+
+	TEST_SUCC(dm_ioctl(DM_DEV_REMOVE, "verity"));
+}
+END_TEST()
+
+FN_TEST(invalid_tables)
+{
+	const struct target gap[] = {
+		{ 0, 1024, "linear", "/dev/vda1 0" },
+		{ 2048, 1024, "linear", "/dev/vda2 0" },
+	};
+	const struct target too_large[] = {
+		{ 0, PART_SECTORS + 1, "linear", "/dev/vda1 0" },
+	};
+	const struct target no_device[] = {
+		{ 0, 1024, "linear", "/dev/nonexistent 0" },
+	};
+	const struct target bad_type[] = {
+		{ 0, 1024, "unknown", "/dev/vda1 0" },
+	};
+	const struct target bad_chunk[] = {
+		{ 0, 1024, "striped", "2 3 /dev/vda1 0 /dev/vda2 0" },
+	};
+	const struct target self[] = {
+		{ 0, 1024, "linear", "/dev/mapper/bad 0" },
+	};
+
+	TEST_SUCC(dm_ioctl(DM_DEV_CREATE, "bad"));
+	TEST_ERRNO(dm_ioctl(DM_DEV_CREATE, "bad"), EBUSY);
+
+	TEST_ERRNO(load_table("bad", gap, 2), EINVAL);
+	TEST_ERRNO(load_table("bad", too_large, 1), EINVAL);
+	TEST_ERRNO(load_table("bad", no_device, 1), ENXIO);
+	TEST_ERRNO(load_table("bad", bad_type, 1), EINVAL);
+	TEST_ERRNO(load_table("bad", bad_chunk, 1), EINVAL);
+	TEST_ERRNO(load_table("bad", self, 1), EINVAL);
+
+	// The device without a table is empty.
+	TEST_RES(dm_ioctl(DM_DEV_STATUS, "bad"),
+		 !(req.hdr.flags &
+		   (DM_ACTIVE_PRESENT_FLAG | DM_INACTIVE_PRESENT_FLAG)));
+	TEST_RES(device_size("/dev/mapper/bad"), _ret == 0);
+
+	TEST_SUCC(dm_ioctl(DM_DEV_REMOVE, "bad"));
+	TEST_ERRNO(dm_ioctl(DM_DEV_STATUS, "bad"), ENXIO);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	memset(sector, 0, SECTOR_SIZE);
+	CHECK_WITH(pwrite(disk_fd, sector, SECTOR_SIZE, 0),
+		   _ret == SECTOR_SIZE);
+	CHECK(ioctl(disk_fd, BLKRRPART));
+	CHECK(close(control_fd));
+	CHECK(close(disk_fd));
+}
+END_SETUP()
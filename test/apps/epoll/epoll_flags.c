@@ -0,0 +1,186 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <stdint.h>
+#include <sys/epoll.h>
+#include <sys/eventfd.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static int efd;
+
+FN_SETUP(eventfd)
+{
+	efd = CHECK(eventfd(0, EFD_NONBLOCK));
+}
+END_SETUP()
+
+static int notify(void)
+{
+	uint64_t val = 1;
+
+	return write(efd, &val, sizeof(val)) == sizeof(val) ? 0 : -1;
+}
+
+static int consume(void)
+{
+	uint64_t val;
+
+	return read(efd, &val, sizeof(val)) == sizeof(val) ? 0 : -1;
+}
+
+static int epoll_add(int epfd, int fd, uint32_t events)
+{
+	struct epoll_event ev = { .events = events, .data.fd = fd };
+
+	return epoll_ctl(epfd, EPOLL_CTL_ADD, fd, &ev);
+}
+
+static int epoll_mod(int epfd, int fd, uint32_t events)
+{
+	struct epoll_event ev = { .events = events, .data.fd = fd };
+
+	return epoll_ctl(epfd, EPOLL_CTL_MOD, fd, &ev);
+}
+
+static int epoll_ready(int epfd)
+{
+	struct epoll_event ev;
+
+	return epoll_wait(epfd, &ev, 1, 0);
+}
+
+FN_TEST(edge_triggered)
+{
+	int epfd;
+
+	epfd = TEST_SUCC(epoll_create1(0));
+	TEST_SUCC(epoll_add(epfd, efd, EPOLLIN | EPOLLET));
+	TEST_RES(epoll_ready(epfd), _ret == 0);
+
+	// The event is reported only once.
+	TEST_SUCC(notify());
+	TEST_RES(epoll_ready(epfd), _ret == 1);
+	TEST_RES(epoll_ready(epfd), _ret == 0);
+
+	// A new event is reported even if the old one is not consumed.
+	TEST_SUCC(notify());
+	TEST_RES(epoll_ready(epfd), _ret == 1);
+	TEST_RES(epoll_ready(epfd), _ret == 0);
+
+	TEST_SUCC(consume());
+	TEST_RES(epoll_ready(epfd), _ret == 0);
+
+	TEST_SUCC(close(epfd));
+}
+END_TEST()
+
+FN_TEST(one_shot)
+{
+	int epfd;
+
+	epfd = TEST_SUCC(epoll_create1(0));
+	TEST_SUCC(epoll_add(epfd, efd, EPOLLIN | EPOLLONESHOT));
+
+	TEST_SUCC(notify());
+	TEST_RES(epoll_ready(epfd), _ret == 1);
+	TEST_RES(epoll_ready(epfd), _ret == 0);
+
+	// The entry is disabled until it is re-armed.
+	TEST_SUCC(notify());
+	TEST_RES(epoll_ready(epfd), _ret == 0);
+
+	TEST_SUCC(epoll_mod(epfd, efd, EPOLLIN | EPOLLONESHOT));
+	TEST_RES(epoll_ready(epfd), _ret == 1);
+	TEST_RES(epoll_ready(epfd), _ret == 0);
+
+	TEST_SUCC(consume());
+	TEST_SUCC(close(epfd));
+}
+END_TEST()
+
+FN_TEST(exclusive_invalid)
+{
+	int epfd, epfd2;
+
+	epfd = TEST_SUCC(epoll_create1(0));
+	epfd2 = TEST_SUCC(epoll_create1(0));
+
+	TEST_ERRNO(epoll_add(epfd, epfd, EPOLLIN), EINVAL);
+	TEST_ERRNO(epoll_add(epfd, epfd2, EPOLLIN | EPOLLEXCLUSIVE), EINVAL);
+	TEST_ERRNO(epoll_add(epfd, efd, EPOLLIN | EPOLLEXCLUSIVE | EPOLLONESHOT),
+		   EINVAL);
+	TEST_ERRNO(epoll_add(epfd, efd, EPOLLIN | EPOLLEXCLUSIVE | EPOLLRDHUP),
+		   EINVAL);
+
+	TEST_SUCC(epoll_add(epfd, efd, EPOLLIN));
+	TEST_ERRNO(epoll_mod(epfd, efd, EPOLLIN | EPOLLEXCLUSIVE), EINVAL);
+	TEST_SUCC(epoll_ctl(epfd, EPOLL_CTL_DEL, efd, NULL));
+
+	TEST_SUCC(epoll_add(epfd, efd, EPOLLIN | EPOLLET | EPOLLEXCLUSIVE));
+	TEST_ERRNO(epoll_mod(epfd, efd, EPOLLIN), EINVAL);
+
+	TEST_SUCC(close(epfd));
+	TEST_SUCC(close(epfd2));
+}
+END_TEST()
+
+static pid_t spawn_waiter(int fd)
+{
+	struct epoll_event ev;
+	int epfd;
+	pid_t pid;
+
+	pid = fork();
+	if (pid != 0)
+		return pid;
+
+	epfd = CHECK(epoll_create1(0));
+	CHECK(epoll_add(epfd, fd, EPOLLIN | EPOLLET | EPOLLEXCLUSIVE));
+	CHECK_WITH(epoll_wait(epfd, &ev, 1, 5000), _ret == 1);
+	_exit(0);
+}
+
+static pid_t waiters[2];
+
+static int reap_waiters(void)
+{
+	int i, status, count = 0;
+
+	for (i = 0; i < 2; ++i) {
+		if (waiters[i] > 0 && waitpid(waiters[i], &status, WNOHANG) > 0) {
+			waiters[i] = 0;
+			++count;
+		}
+	}
+
+	return count;
+}
+
+FN_TEST(exclusive_wakeup)
+{
+	int fildes[2];
+
+	// Linux does not wake up eventfd waiters exclusively, so a pipe is used here.
+	TEST_SUCC(pipe(fildes));
+	waiters[0] = TEST_SUCC(spawn_waiter(fildes[0]));
+	waiters[1] = TEST_SUCC(spawn_waiter(fildes[0]));
+	usleep(200 * 1000);
+
+	// Only one of the waiters is woken up.
+	TEST_RES(write(fildes[1], "a", 1), _ret == 1);
+	usleep(200 * 1000);
+	TEST_RES(reap_waiters(), _ret == 1);
+
+	// The other waiter is woken up by the next event.
+	TEST_RES(write(fildes[1], "a", 1), _ret == 1);
+	usleep(200 * 1000);
+	TEST_RES(reap_waiters(), _ret == 1);
+
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+}
+END_TEST()
@@ -0,0 +1,123 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <stdint.h>
+#include <sys/epoll.h>
+#include <sys/eventfd.h>
+#include <unistd.h>
+
+#define MAX_NESTS 4
+
+static int efd;
+
+FN_SETUP(eventfd)
+{
+	efd = CHECK(eventfd(0, EFD_NONBLOCK));
+}
+END_SETUP()
+
+static int notify(void)
+{
+	uint64_t val = 1;
+
+	return write(efd, &val, sizeof(val)) == sizeof(val) ? 0 : -1;
+}
+
+static int consume(void)
+{
+	uint64_t val;
+
+	return read(efd, &val, sizeof(val)) == sizeof(val) ? 0 : -1;
+}
+
+static int epoll_add(int epfd, int fd)
+{
+	struct epoll_event ev = { .events = EPOLLIN, .data.fd = fd };
+
+	return epoll_ctl(epfd, EPOLL_CTL_ADD, fd, &ev);
+}
+
+static int epoll_ready(int epfd)
+{
+	struct epoll_event ev;
+
+	return epoll_wait(epfd, &ev, 1, 0);
+}
+
+FN_TEST(nested_events)
+{
+	int inner, outer;
+
+	inner = TEST_SUCC(epoll_create1(0));
+	outer = TEST_SUCC(epoll_create1(0));
+	TEST_SUCC(epoll_add(inner, efd));
+	TEST_SUCC(epoll_add(outer, inner));
+	TEST_RES(epoll_ready(outer), _ret == 0);
+
+	TEST_SUCC(notify());
+	TEST_RES(epoll_ready(outer), _ret == 1);
+	TEST_RES(epoll_ready(outer), _ret == 1);
+
+	// The outer epoll file is not ready after the events are consumed.
+	TEST_SUCC(consume());
+	TEST_RES(epoll_ready(outer), _ret == 0);
+	TEST_RES(epoll_ready(inner), _ret == 0);
+
+	TEST_SUCC(close(outer));
+	TEST_SUCC(close(inner));
+}
+END_TEST()
+
+FN_TEST(nested_loops)
+{
+	int epfd1, epfd2, epfd3;
+
+	epfd1 = TEST_SUCC(epoll_create1(0));
+	epfd2 = TEST_SUCC(epoll_create1(0));
+	epfd3 = TEST_SUCC(epoll_create1(0));
+
+	TEST_SUCC(epoll_add(epfd1, epfd2));
+	TEST_ERRNO(epoll_add(epfd2, epfd1), ELOOP);
+
+	TEST_SUCC(epoll_add(epfd2, epfd3));
+	TEST_ERRNO(epoll_add(epfd3, epfd1), ELOOP);
+	TEST_ERRNO(epoll_add(epfd3, epfd2), ELOOP);
+
+	// The loop is gone after the entry is deleted.
+	TEST_SUCC(epoll_ctl(epfd1, EPOLL_CTL_DEL, epfd2, NULL));
+	TEST_SUCC(epoll_add(epfd3, epfd1));
+
+	TEST_SUCC(close(epfd1));
+	TEST_SUCC(close(epfd2));
+	TEST_SUCC(close(epfd3));
+}
+END_TEST()
+
+FN_TEST(nested_depth)
+{
+	int epfds[MAX_NESTS + 2];
+	int i;
+
+	for (i = 0; i < MAX_NESTS + 2; i++)
+		epfds[i] = TEST_SUCC(epoll_create1(0));
+
+	// Build the chain from both ends, so that the depths in both directions are counted.
+	for (i = 2; i < MAX_NESTS; i++)
+		TEST_SUCC(epoll_add(epfds[i], epfds[i + 1]));
+	TEST_SUCC(epoll_add(epfds[0], epfds[1]));
+	TEST_SUCC(epoll_add(epfds[1], epfds[2]));
+
+	TEST_ERRNO(epoll_add(epfds[MAX_NESTS], epfds[MAX_NESTS + 1]), ELOOP);
+	TEST_ERRNO(epoll_add(epfds[MAX_NESTS + 1], epfds[0]), ELOOP);
+
+	// The chain becomes shorter after the outermost epoll file is closed.
+	TEST_SUCC(close(epfds[0]));
+	TEST_SUCC(epoll_add(epfds[MAX_NESTS], epfds[MAX_NESTS + 1]));
+
+	for (i = 1; i < MAX_NESTS + 2; i++)
+		TEST_SUCC(close(epfds[i]));
+}
+END_TEST()
@@ -0,0 +1,184 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <signal.h>
+#include <stdint.h>
+#include <string.h>
+#include <sys/epoll.h>
+#include <sys/eventfd.h>
+#include <sys/signalfd.h>
+#include <sys/timerfd.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+static int epfd;
+
+FN_SETUP(epoll)
+{
+	epfd = CHECK(epoll_create1(0));
+}
+END_SETUP()
+
+static int epoll_add(int fd)
+{
+	struct epoll_event ev = { .events = EPOLLIN, .data.fd = fd };
+
+	return epoll_ctl(epfd, EPOLL_CTL_ADD, fd, &ev);
+}
+
+static int epoll_wait_fd(int timeout)
+{
+	struct epoll_event ev;
+	int ret;
+
+	ret = epoll_wait(epfd, &ev, 1, timeout);
+	if (ret <= 0)
+		return ret;
+
+	return ev.data.fd;
+}
+
+FN_TEST(eventfd)
+{
+	uint64_t val;
+	int efd;
+
+	efd = TEST_SUCC(eventfd(0, EFD_NONBLOCK | EFD_SEMAPHORE));
+	TEST_SUCC(epoll_add(efd));
+	TEST_RES(epoll_wait_fd(0), _ret == 0);
+
+	val = 2;
+	TEST_RES(write(efd, &val, sizeof(val)), _ret == sizeof(val));
+	TEST_RES(epoll_wait_fd(0), _ret == efd);
+
+	TEST_RES(read(efd, &val, sizeof(val)), _ret == sizeof(val) && val == 1);
+	TEST_RES(read(efd, &val, sizeof(val)), _ret == sizeof(val) && val == 1);
+	TEST_ERRNO(read(efd, &val, sizeof(val)), EAGAIN);
+	TEST_RES(epoll_wait_fd(0), _ret == 0);
+
+	val = UINT64_MAX;
+	TEST_ERRNO(write(efd, &val, sizeof(val)), EINVAL);
+	val = UINT64_MAX - 1;
+	TEST_RES(write(efd, &val, sizeof(val)), _ret == sizeof(val));
+	val = 1;
+	TEST_ERRNO(write(efd, &val, sizeof(val)), EAGAIN);
+
+	TEST_SUCC(close(efd));
+}
+END_TEST()
+
+FN_TEST(timerfd)
+{
+	struct itimerspec its = { .it_value = { .tv_nsec = 1000 * 1000 } };
+	uint64_t ticks;
+	int tfd;
+
+	TEST_ERRNO(timerfd_create(CLOCK_PROCESS_CPUTIME_ID, 0), EINVAL);
+	TEST_ERRNO(timerfd_create(CLOCK_MONOTONIC, 1), EINVAL);
+
+	tfd = TEST_SUCC(timerfd_create(CLOCK_MONOTONIC, TFD_NONBLOCK));
+	TEST_SUCC(epoll_add(tfd));
+	TEST_ERRNO(read(tfd, &ticks, sizeof(ticks)), EAGAIN);
+
+	TEST_SUCC(timerfd_settime(tfd, 0, &its, NULL));
+	TEST_RES(epoll_wait_fd(1000), _ret == tfd);
+	TEST_RES(read(tfd, &ticks, sizeof(ticks)),
+		 _ret == sizeof(ticks) && ticks == 1);
+	TEST_RES(epoll_wait_fd(0), _ret == 0);
+
+	TEST_ERRNO(write(tfd, &ticks, sizeof(ticks)), EINVAL);
+
+	TEST_SUCC(close(tfd));
+}
+END_TEST()
+
+static int sigusr_mask(sigset_t *mask)
+{
+	sigemptyset(mask);
+	sigaddset(mask, SIGUSR1);
+	sigaddset(mask, SIGUSR2);
+	return sigprocmask(SIG_BLOCK, mask, NULL);
+}
+
+FN_TEST(signalfd)
+{
+	struct signalfd_siginfo ssi;
+	union sigval value = { .sival_int = 42 };
+	sigset_t mask;
+	int sfd;
+
+	TEST_SUCC(sigusr_mask(&mask));
+	TEST_ERRNO(signalfd(-1, &mask, 4), EINVAL);
+
+	sfd = TEST_SUCC(signalfd(-1, &mask, SFD_NONBLOCK));
+	TEST_SUCC(epoll_add(sfd));
+	TEST_RES(epoll_wait_fd(0), _ret == 0);
+	TEST_ERRNO(read(sfd, &ssi, sizeof(ssi)), EAGAIN);
+
+	TEST_SUCC(kill(getpid(), SIGUSR1));
+	TEST_RES(epoll_wait_fd(0), _ret == sfd);
+	TEST_RES(read(sfd, &ssi, sizeof(ssi)),
+		 _ret == sizeof(ssi) && ssi.ssi_signo == SIGUSR1 &&
+			 ssi.ssi_code == SI_USER && ssi.ssi_pid == getpid() &&
+			 ssi.ssi_uid == getuid());
+
+	TEST_SUCC(sigqueue(getpid(), SIGUSR2, value));
+	TEST_RES(read(sfd, &ssi, sizeof(ssi)),
+		 _ret == sizeof(ssi) && ssi.ssi_signo == SIGUSR2 &&
+			 ssi.ssi_code == SI_QUEUE && ssi.ssi_int == 42);
+	TEST_RES(epoll_wait_fd(0), _ret == 0);
+
+	// Signals outside the mask are not reported.
+	sigemptyset(&mask);
+	sigaddset(&mask, SIGUSR2);
+	TEST_RES(signalfd(sfd, &mask, 0), _ret == sfd);
+	TEST_SUCC(kill(getpid(), SIGUSR1));
+	TEST_RES(epoll_wait_fd(0), _ret == 0);
+	TEST_ERRNO(read(sfd, &ssi, sizeof(ssi)), EAGAIN);
+
+	sigemptyset(&mask);
+	sigaddset(&mask, SIGUSR1);
+	TEST_RES(signalfd(sfd, &mask, 0), _ret == sfd);
+	TEST_RES(read(sfd, &ssi, sizeof(ssi)),
+		 _ret == sizeof(ssi) && ssi.ssi_signo == SIGUSR1);
+
+	TEST_SUCC(close(sfd));
+}
+END_TEST()
+
+FN_TEST(signalfd_after_fork)
+{
+	struct signalfd_siginfo ssi;
+	sigset_t mask;
+	int sfd, status;
+	pid_t pid;
+
+	TEST_SUCC(sigusr_mask(&mask));
+	sfd = TEST_SUCC(signalfd(-1, &mask, 0));
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		int child_epfd;
+		struct epoll_event ev = { .events = EPOLLIN };
+
+		// The child must be notified of its own signals.
+		child_epfd = CHECK(epoll_create1(0));
+		CHECK(epoll_ctl(child_epfd, EPOLL_CTL_ADD, sfd, &ev));
+		CHECK_WITH(epoll_wait(child_epfd, &ev, 1, 5000), _ret == 1);
+		CHECK_WITH(read(sfd, &ssi, sizeof(ssi)),
+			   _ret == sizeof(ssi) && ssi.ssi_signo == SIGUSR2);
+		_exit(0);
+	}
+
+	usleep(100 * 1000);
+	TEST_SUCC(kill(pid, SIGUSR2));
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+
+	TEST_SUCC(close(sfd));
+}
+END_TEST()
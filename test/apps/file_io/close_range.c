@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <sched.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#ifndef CLOSE_RANGE_UNSHARE
+#define CLOSE_RANGE_UNSHARE (1U << 1)
+#endif
+#ifndef CLOSE_RANGE_CLOEXEC
+#define CLOSE_RANGE_CLOEXEC (1U << 2)
+#endif
+
+static int do_close_range(unsigned int first, unsigned int last,
+			  unsigned int flags)
+{
+	return syscall(SYS_close_range, first, last, flags);
+}
+
+static int fds[3];
+
+FN_SETUP(open_files)
+{
+	for (int i = 0; i < 3; i++)
+		fds[i] = CHECK(open("/dev/null", O_RDONLY));
+}
+END_SETUP()
+
+FN_TEST(invalid_args)
+{
+	TEST_ERRNO(do_close_range(fds[1], fds[0], 0), EINVAL);
+	TEST_ERRNO(do_close_range(fds[0], fds[2], 1U << 0), EINVAL);
+}
+END_TEST()
+
+FN_TEST(set_cloexec)
+{
+	TEST_SUCC(do_close_range(fds[0], fds[1], CLOSE_RANGE_CLOEXEC));
+
+	TEST_RES(fcntl(fds[0], F_GETFD), _ret == FD_CLOEXEC);
+	TEST_RES(fcntl(fds[1], F_GETFD), _ret == FD_CLOEXEC);
+	TEST_RES(fcntl(fds[2], F_GETFD), _ret == 0);
+
+	TEST_SUCC(fcntl(fds[0], F_SETFD, 0));
+	TEST_SUCC(fcntl(fds[1], F_SETFD, 0));
+}
+END_TEST()
+
+static char child_stack[4096];
+#define CHILD_STACK_TOP \
+	(child_stack + sizeof(child_stack) / sizeof(child_stack[0]))
+
+static int child_unshare_and_close(void *arg)
+{
+	if (do_close_range(fds[1], ~0U, CLOSE_RANGE_UNSHARE) < 0)
+		_exit(1);
+	if (fcntl(fds[1], F_GETFD) >= 0 || fcntl(fds[0], F_GETFD) < 0)
+		_exit(2);
+
+	_exit(0);
+}
+
+FN_TEST(unshare_and_close)
+{
+	pid_t pid;
+	int status;
+
+	pid = TEST_SUCC(clone(&child_unshare_and_close, CHILD_STACK_TOP,
+			      CLONE_FILES | SIGCHLD, NULL, NULL, NULL, NULL));
+	TEST_RES(wait(&status),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+
+	// The files are closed only in the unshared file table of the child.
+	TEST_SUCC(fcntl(fds[1], F_GETFD));
+	TEST_SUCC(fcntl(fds[2], F_GETFD));
+}
+END_TEST()
+
+FN_TEST(close_files)
+{
+	TEST_SUCC(do_close_range(fds[1], ~0U, 0));
+
+	TEST_SUCC(fcntl(fds[0], F_GETFD));
+	TEST_ERRNO(fcntl(fds[1], F_GETFD), EBADF);
+	TEST_ERRNO(fcntl(fds[2], F_GETFD), EBADF);
+
+	TEST_SUCC(close(fds[0]));
+}
+END_TEST()
+
+FN_TEST(pipe2_flags)
+{
+	int pipefds[2];
+
+	TEST_SUCC(pipe2(pipefds, O_CLOEXEC | O_NONBLOCK));
+	TEST_RES(fcntl(pipefds[0], F_GETFD), _ret == FD_CLOEXEC);
+	TEST_RES(fcntl(pipefds[1], F_GETFL), (_ret & O_NONBLOCK) != 0);
+	TEST_SUCC(close(pipefds[0]));
+	TEST_SUCC(close(pipefds[1]));
+
+	TEST_ERRNO(pipe2(pipefds, O_APPEND), EINVAL);
+}
+END_TEST()
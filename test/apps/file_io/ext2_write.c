@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/statvfs.h>
+#include <unistd.h>
+
+#define DIR_NAME "/ext2/ext2_write_test"
+#define FILE_NAME DIR_NAME "/file"
+#define FIFO_NAME DIR_NAME "/fifo"
+
+// The offset that requires the double indirect blocks with 4 KiB blocks.
+#define FAR_OFFSET (5 * 1024 * 1024)
+
+static int dir_fd;
+static unsigned long nr_free_blocks;
+
+static unsigned long free_blocks(void)
+{
+	struct statvfs buf;
+
+	if (fstatvfs(dir_fd, &buf) < 0)
+		return 0;
+	return buf.f_bfree;
+}
+
+static int read_is(int fd, off_t offset, const char *expected)
+{
+	char buf[16];
+	size_t len = strlen(expected);
+
+	if (pread(fd, buf, len, offset) != len)
+		return 0;
+	return memcmp(buf, expected, len) == 0;
+}
+
+FN_SETUP(init)
+{
+	CHECK(mkdir(DIR_NAME, 0755));
+	dir_fd = CHECK(open(DIR_NAME, O_RDONLY | O_DIRECTORY));
+	nr_free_blocks = free_blocks();
+}
+END_SETUP()
+
+FN_TEST(write_and_fsync)
+{
+	int fd;
+	struct stat st;
+
+	fd = TEST_SUCC(open(FILE_NAME, O_CREAT | O_RDWR, 0644));
+	TEST_RES(pwrite(fd, "head", 4, 0), _ret == 4);
+	TEST_RES(pwrite(fd, "tail", 4, FAR_OFFSET), _ret == 4);
+	TEST_SUCC(fsync(fd));
+	TEST_SUCC(fsync(dir_fd));
+
+	TEST_RES(fstat(fd, &st), st.st_size == FAR_OFFSET + 4);
+	TEST_RES(free_blocks(), _ret < nr_free_blocks);
+	TEST_RES(read_is(fd, 0, "head"), _ret);
+	TEST_RES(read_is(fd, FAR_OFFSET, "tail"), _ret);
+	TEST_RES(read_is(fd, FAR_OFFSET / 2, "\0\0\0\0"), _ret);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(truncate_frees_blocks)
+{
+	int fd;
+	struct stat st;
+
+	fd = TEST_SUCC(open(FILE_NAME, O_RDWR));
+	TEST_SUCC(ftruncate(fd, 4096));
+	TEST_SUCC(fdatasync(fd));
+	TEST_RES(fstat(fd, &st), st.st_size == 4096);
+	TEST_RES(read_is(fd, 0, "head"), _ret);
+
+	TEST_SUCC(ftruncate(fd, 0));
+	TEST_SUCC(fsync(fd));
+	TEST_RES(fstat(fd, &st), st.st_size == 0 && st.st_blocks == 0);
+	TEST_RES(free_blocks(), _ret == nr_free_blocks);
+
+	TEST_SUCC(close(fd));
+	TEST_SUCC(unlink(FILE_NAME));
+}
+END_TEST()
+
+FN_TEST(mknod_fifo)
+{
+	struct stat st;
+
+	TEST_SUCC(mknod(FIFO_NAME, S_IFIFO | 0644, 0));
+	TEST_RES(stat(FIFO_NAME, &st), S_ISFIFO(st.st_mode));
+	TEST_ERRNO(mknod(FIFO_NAME, S_IFIFO | 0644, 0), EEXIST);
+	TEST_SUCC(unlink(FIFO_NAME));
+	TEST_ERRNO(stat(FIFO_NAME, &st), ENOENT);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(dir_fd));
+	CHECK(rmdir(DIR_NAME));
+}
+END_SETUP()
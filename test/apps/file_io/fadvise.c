@@ -0,0 +1,165 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define EXT2_FILE "/ext2/fadvise_test"
+#define TMP_FILE "/tmp/fadvise_test"
+#define FIFO_NAME "/tmp/fadvise_fifo"
+
+#define PAGE_SIZE 4096
+#define NR_PAGES 16
+#define FILE_SIZE (NR_PAGES * PAGE_SIZE)
+
+// Unlike `posix_fadvise`, the system call sets `errno` on failure.
+#define fadvise(fd, offset, len, advice) \
+	syscall(SYS_fadvise64, fd, offset, len, advice)
+
+static char buf[FILE_SIZE];
+
+static void fill_pattern(char *dst, char seed)
+{
+	int i;
+
+	for (i = 0; i < FILE_SIZE; i++)
+		dst[i] = seed + i % 23;
+}
+
+static int read_matches(int fd, char seed)
+{
+	char expected[FILE_SIZE];
+
+	fill_pattern(expected, seed);
+	memset(buf, 0, sizeof(buf));
+	if (pread(fd, buf, FILE_SIZE, 0) != FILE_SIZE)
+		return 0;
+	return memcmp(buf, expected, FILE_SIZE) == 0;
+}
+
+static int write_pattern(int fd, char seed)
+{
+	fill_pattern(buf, seed);
+	return pwrite(fd, buf, FILE_SIZE, 0) == FILE_SIZE;
+}
+
+static int ext2_fd, tmp_fd;
+
+FN_SETUP(open_files)
+{
+	ext2_fd = CHECK(open(EXT2_FILE, O_RDWR | O_CREAT | O_TRUNC, 0644));
+	tmp_fd = CHECK(open(TMP_FILE, O_RDWR | O_CREAT | O_TRUNC, 0644));
+	CHECK(mkfifo(FIFO_NAME, 0644));
+}
+END_SETUP()
+
+FN_TEST(invalid_args)
+{
+	int fildes[2];
+	int fifo_fd;
+
+	TEST_ERRNO(fadvise(ext2_fd, 0, 0, 6), EINVAL);
+	TEST_ERRNO(fadvise(ext2_fd, 0, 0, -1), EINVAL);
+	TEST_ERRNO(fadvise(ext2_fd, 0, -1, POSIX_FADV_NORMAL), EINVAL);
+	TEST_ERRNO(fadvise(-1, 0, 0, POSIX_FADV_NORMAL), EBADF);
+
+	TEST_SUCC(pipe(fildes));
+	TEST_ERRNO(fadvise(fildes[0], 0, 0, POSIX_FADV_NORMAL), ESPIPE);
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+
+	fifo_fd = TEST_SUCC(open(FIFO_NAME, O_RDONLY | O_NONBLOCK));
+	TEST_ERRNO(fadvise(fifo_fd, 0, 0, POSIX_FADV_DONTNEED), ESPIPE);
+	TEST_SUCC(close(fifo_fd));
+
+	// The libc wrapper returns the error number instead of setting `errno`.
+	TEST_RES(posix_fadvise(ext2_fd, 0, 0, 6), _ret == EINVAL);
+	TEST_RES(posix_fadvise(ext2_fd, 0, 0, POSIX_FADV_NOREUSE), _ret == 0);
+	TEST_RES(posix_fadvise(ext2_fd, 0, 0, POSIX_FADV_WILLNEED), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(readahead_hints)
+{
+	TEST_RES(write_pattern(ext2_fd, 'a'), _ret);
+	TEST_SUCC(fsync(ext2_fd));
+
+	TEST_SUCC(fadvise(ext2_fd, 0, 0, POSIX_FADV_DONTNEED));
+	TEST_SUCC(fadvise(ext2_fd, 0, 0, POSIX_FADV_SEQUENTIAL));
+	TEST_RES(read_matches(ext2_fd, 'a'), _ret);
+
+	TEST_SUCC(fadvise(ext2_fd, 0, 0, POSIX_FADV_DONTNEED));
+	TEST_SUCC(fadvise(ext2_fd, 0, 0, POSIX_FADV_RANDOM));
+	TEST_RES(read_matches(ext2_fd, 'a'), _ret);
+
+	TEST_SUCC(fadvise(ext2_fd, 0, 0, POSIX_FADV_NORMAL));
+	TEST_RES(read_matches(ext2_fd, 'a'), _ret);
+}
+END_TEST()
+
+FN_TEST(dontneed_keeps_data)
+{
+	char *addr;
+
+	// The clean pages are read again from the disk.
+	TEST_RES(write_pattern(ext2_fd, 'b'), _ret);
+	TEST_SUCC(fdatasync(ext2_fd));
+	TEST_SUCC(fadvise(ext2_fd, PAGE_SIZE / 2, FILE_SIZE,
+			  POSIX_FADV_DONTNEED));
+	TEST_RES(read_matches(ext2_fd, 'b'), _ret);
+
+	// The dirty pages are kept.
+	TEST_RES(write_pattern(ext2_fd, 'c'), _ret);
+	TEST_SUCC(fadvise(ext2_fd, 0, 0, POSIX_FADV_DONTNEED));
+	TEST_RES(read_matches(ext2_fd, 'c'), _ret);
+	TEST_SUCC(fsync(ext2_fd));
+
+	// The mapped pages are kept.
+	addr = (char *)TEST_RES((long)mmap(NULL, FILE_SIZE,
+					   PROT_READ | PROT_WRITE, MAP_SHARED,
+					   ext2_fd, 0),
+				_ret != (long)MAP_FAILED);
+	TEST_RES(addr[0], _ret == 'c');
+	TEST_SUCC(fadvise(ext2_fd, 0, 0, POSIX_FADV_DONTNEED));
+	addr[0] = 'x';
+	TEST_RES(pread(ext2_fd, buf, 1, 0), _ret == 1 && buf[0] == 'x');
+	TEST_SUCC(munmap(addr, FILE_SIZE));
+}
+END_TEST()
+
+FN_TEST(dontneed_in_memory)
+{
+	char *addr;
+
+	// The pages of in-memory files are never dropped, even if they have
+	// been "written back".
+	TEST_SUCC(ftruncate(tmp_fd, FILE_SIZE));
+	addr = (char *)TEST_RES((long)mmap(NULL, FILE_SIZE,
+					   PROT_READ | PROT_WRITE, MAP_SHARED,
+					   tmp_fd, 0),
+				_ret != (long)MAP_FAILED);
+	fill_pattern(addr, 'd');
+	TEST_SUCC(msync(addr, FILE_SIZE, MS_SYNC));
+	TEST_SUCC(munmap(addr, FILE_SIZE));
+
+	TEST_SUCC(fadvise(tmp_fd, 0, 0, POSIX_FADV_DONTNEED));
+	TEST_RES(read_matches(tmp_fd, 'd'), _ret);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(ext2_fd));
+	CHECK(close(tmp_fd));
+	CHECK(unlink(EXT2_FILE));
+	CHECK(unlink(TMP_FILE));
+	CHECK(unlink(FIFO_NAME));
+}
+END_SETUP()
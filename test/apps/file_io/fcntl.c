@@ -0,0 +1,156 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <sys/ioctl.h>
+#include <sys/resource.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#define EXT2_FILE "/ext2/fcntl_test"
+#define TMP_FIFO "/tmp/fcntl_fifo"
+
+static int file_fd;
+
+FN_SETUP(create)
+{
+	file_fd = CHECK(open(EXT2_FILE, O_RDWR | O_CREAT | O_TRUNC, 0644));
+	CHECK(mkfifo(TMP_FIFO, 0644));
+}
+END_SETUP()
+
+FN_TEST(dupfd)
+{
+	struct rlimit rlim;
+	int fd;
+
+	fd = TEST_RES(fcntl(file_fd, F_DUPFD_CLOEXEC, 100), _ret == 100);
+	TEST_RES(fcntl(fd, F_GETFD), _ret == FD_CLOEXEC);
+	TEST_SUCC(close(fd));
+
+	fd = TEST_RES(fcntl(file_fd, F_DUPFD, 100), _ret == 100);
+	TEST_RES(fcntl(fd, F_GETFD), _ret == 0);
+	TEST_SUCC(close(fd));
+
+	// The minimum file descriptor must be within the limit.
+	TEST_SUCC(getrlimit(RLIMIT_NOFILE, &rlim));
+	TEST_ERRNO(fcntl(file_fd, F_DUPFD, rlim.rlim_cur), EINVAL);
+	TEST_ERRNO(fcntl(file_fd, F_DUPFD_CLOEXEC, rlim.rlim_cur), EINVAL);
+	TEST_ERRNO(fcntl(file_fd, F_DUPFD, -1), EINVAL);
+	TEST_ERRNO(fcntl(-1, F_DUPFD, 0), EBADF);
+}
+END_TEST()
+
+FN_TEST(setfl)
+{
+	int enabled;
+
+	// The access mode and the creation flags are ignored.
+	TEST_SUCC(fcntl(file_fd, F_SETFL,
+			O_RDONLY | O_CREAT | O_APPEND | O_NONBLOCK));
+	TEST_RES(fcntl(file_fd, F_GETFL),
+		 _ret == (O_RDWR | O_APPEND | O_NONBLOCK));
+
+	// The other status flags are kept.
+	enabled = 0;
+	TEST_SUCC(ioctl(file_fd, FIONBIO, &enabled));
+	TEST_RES(fcntl(file_fd, F_GETFL), _ret == (O_RDWR | O_APPEND));
+	enabled = 1;
+	TEST_SUCC(ioctl(file_fd, FIOASYNC, &enabled));
+	TEST_RES(fcntl(file_fd, F_GETFL),
+		 _ret == (O_RDWR | O_APPEND | O_ASYNC));
+
+	TEST_SUCC(fcntl(file_fd, F_SETFL, 0));
+	TEST_RES(fcntl(file_fd, F_GETFL), _ret == O_RDWR);
+}
+END_TEST()
+
+FN_TEST(pipe_setfl)
+{
+	int fildes[2];
+	int enabled = 1;
+
+	TEST_SUCC(pipe2(fildes, O_DIRECT));
+	TEST_SUCC(ioctl(fildes[1], FIONBIO, &enabled));
+	TEST_RES(fcntl(fildes[1], F_GETFL),
+		 _ret == (O_WRONLY | O_DIRECT | O_NONBLOCK));
+
+	TEST_SUCC(fcntl(fildes[1], F_SETFL, O_APPEND));
+	TEST_RES(fcntl(fildes[1], F_GETFL), _ret == (O_WRONLY | O_APPEND));
+
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+}
+END_TEST()
+
+FN_TEST(pipe_size)
+{
+	int fildes[2];
+	int fd;
+
+	TEST_SUCC(pipe(fildes));
+	TEST_RES(fcntl(fildes[0], F_GETPIPE_SZ), _ret == 65536);
+	TEST_RES(fcntl(fildes[1], F_SETPIPE_SZ, 5000), _ret == 8192);
+	TEST_RES(fcntl(fildes[0], F_GETPIPE_SZ), _ret == 8192);
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+
+	// Named pipes have their sizes as well.
+	fd = TEST_SUCC(open(TMP_FIFO, O_RDWR));
+	TEST_RES(fcntl(fd, F_GETPIPE_SZ), _ret == 65536);
+	TEST_RES(fcntl(fd, F_SETPIPE_SZ, 4096), _ret == 4096);
+	TEST_RES(fcntl(fd, F_GETPIPE_SZ), _ret == 4096);
+	TEST_SUCC(close(fd));
+
+	TEST_ERRNO(fcntl(file_fd, F_GETPIPE_SZ), EBADF);
+	TEST_ERRNO(fcntl(file_fd, F_SETPIPE_SZ, 4096), EBADF);
+}
+END_TEST()
+
+FN_TEST(seals)
+{
+	int fd;
+
+	// Regular files cannot be sealed.
+	TEST_ERRNO(fcntl(file_fd, F_GET_SEALS), EINVAL);
+	TEST_ERRNO(fcntl(file_fd, F_ADD_SEALS, F_SEAL_WRITE), EINVAL);
+
+	fd = TEST_SUCC(open(EXT2_FILE, O_RDONLY));
+	TEST_ERRNO(fcntl(fd, F_ADD_SEALS, F_SEAL_WRITE), EPERM);
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(path_fd)
+{
+	struct flock lock = { .l_type = F_RDLCK, .l_whence = SEEK_SET };
+	int fd, fd2;
+
+	fd = TEST_SUCC(open(EXT2_FILE, O_PATH));
+
+	// Only the commands on the file descriptor itself are allowed.
+	TEST_RES(fcntl(fd, F_GETFL), (_ret & O_PATH) != 0);
+	TEST_SUCC(fcntl(fd, F_SETFD, FD_CLOEXEC));
+	TEST_RES(fcntl(fd, F_GETFD), _ret == FD_CLOEXEC);
+	fd2 = TEST_SUCC(fcntl(fd, F_DUPFD, 0));
+	TEST_SUCC(close(fd2));
+
+	TEST_ERRNO(fcntl(fd, F_SETFL, O_NONBLOCK), EBADF);
+	TEST_ERRNO(fcntl(fd, F_GETLK, &lock), EBADF);
+	TEST_ERRNO(fcntl(fd, F_GETOWN), EBADF);
+	TEST_ERRNO(fcntl(fd, F_GET_SEALS), EBADF);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(file_fd));
+	CHECK(unlink(EXT2_FILE));
+	CHECK(unlink(TMP_FIFO));
+}
+END_SETUP()
@@ -0,0 +1,246 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <poll.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/inotify.h>
+#include <sys/ioctl.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#define DIR_NAME "/tmp/inotify_test"
+#define FILE_NAME DIR_NAME "/file"
+#define NEW_FILE_NAME DIR_NAME "/new_file"
+
+static int ifd;
+static int dir_wd;
+
+static char buf[4096]
+	__attribute__((aligned(__alignof__(struct inotify_event))));
+
+static int nr_events;
+static struct inotify_event *events[16];
+
+// Reads the queued events into `events`, and returns the number of the events.
+static int read_events(void)
+{
+	ssize_t len = read(ifd, buf, sizeof(buf));
+	if (len < 0)
+		return -1;
+
+	nr_events = 0;
+	for (char *ptr = buf; ptr < buf + len && nr_events < 16;) {
+		events[nr_events] = (struct inotify_event *)ptr;
+		ptr += sizeof(struct inotify_event) + events[nr_events]->len;
+		nr_events++;
+	}
+	return nr_events;
+}
+
+static int event_is(int i, int wd, uint32_t mask, const char *name)
+{
+	if (i >= nr_events)
+		return 0;
+	if (events[i]->wd != wd || events[i]->mask != mask)
+		return 0;
+	if (name == NULL)
+		return events[i]->len == 0;
+	return events[i]->len > 0 && strcmp(events[i]->name, name) == 0;
+}
+
+static int create_file(const char *name)
+{
+	int fd = open(name, O_CREAT | O_WRONLY, 0644);
+	if (fd < 0)
+		return -1;
+	return close(fd);
+}
+
+static int open_and_close(const char *name)
+{
+	int fd = open(name, O_RDONLY);
+	if (fd < 0)
+		return -1;
+	return close(fd);
+}
+
+FN_SETUP(init)
+{
+	CHECK(mkdir(DIR_NAME, 0755));
+	ifd = CHECK(inotify_init1(IN_NONBLOCK | IN_CLOEXEC));
+	dir_wd = CHECK(inotify_add_watch(ifd, DIR_NAME,
+					 IN_CREATE | IN_DELETE | IN_MODIFY |
+						 IN_MOVE));
+}
+END_SETUP()
+
+FN_TEST(invalid_args)
+{
+	TEST_ERRNO(inotify_init1(IN_ONESHOT), EINVAL);
+	TEST_ERRNO(inotify_add_watch(ifd, DIR_NAME, 0), EINVAL);
+	TEST_ERRNO(inotify_add_watch(ifd, "/dev/null", IN_ONLYDIR | IN_OPEN),
+		   ENOTDIR);
+	TEST_ERRNO(inotify_add_watch(ifd, DIR_NAME, IN_MASK_CREATE | IN_CREATE),
+		   EEXIST);
+	TEST_ERRNO(inotify_add_watch(ifd, DIR_NAME "/nonexistent", IN_OPEN),
+		   ENOENT);
+	TEST_ERRNO(inotify_add_watch(0, DIR_NAME, IN_OPEN), EINVAL);
+	TEST_ERRNO(inotify_rm_watch(ifd, dir_wd + 100), EINVAL);
+}
+END_TEST()
+
+FN_TEST(no_events)
+{
+	struct pollfd pfd = { .fd = ifd, .events = POLLIN };
+	int nr_bytes;
+
+	TEST_ERRNO(read(ifd, buf, sizeof(buf)), EAGAIN);
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+	TEST_RES(ioctl(ifd, FIONREAD, &nr_bytes), nr_bytes == 0);
+}
+END_TEST()
+
+FN_TEST(create_modify_delete)
+{
+	struct pollfd pfd = { .fd = ifd, .events = POLLIN };
+	int fd;
+	int nr_bytes;
+
+	fd = TEST_SUCC(open(FILE_NAME, O_CREAT | O_WRONLY, 0644));
+	TEST_RES(write(fd, "hello", 5), _ret == 5);
+	TEST_SUCC(close(fd));
+
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && pfd.revents == POLLIN);
+	TEST_RES(ioctl(ifd, FIONREAD, &nr_bytes),
+		 nr_bytes > 2 * sizeof(struct inotify_event));
+
+	// A small buffer cannot hold the first event.
+	TEST_ERRNO(read(ifd, buf, sizeof(struct inotify_event)), EINVAL);
+
+	TEST_RES(read_events(),
+		 _ret == 2 && event_is(0, dir_wd, IN_CREATE, "file") &&
+			 event_is(1, dir_wd, IN_MODIFY, "file"));
+
+	TEST_SUCC(unlink(FILE_NAME));
+	TEST_RES(read_events(),
+		 _ret == 1 && event_is(0, dir_wd, IN_DELETE, "file"));
+	TEST_ERRNO(read(ifd, buf, sizeof(buf)), EAGAIN);
+}
+END_TEST()
+
+FN_TEST(mkdir_rmdir)
+{
+	TEST_SUCC(mkdir(DIR_NAME "/dir", 0755));
+	TEST_SUCC(rmdir(DIR_NAME "/dir"));
+
+	TEST_RES(read_events(),
+		 _ret == 2 && event_is(0, dir_wd, IN_CREATE | IN_ISDIR, "dir") &&
+			 event_is(1, dir_wd, IN_DELETE | IN_ISDIR, "dir"));
+}
+END_TEST()
+
+FN_TEST(rename)
+{
+	TEST_SUCC(create_file(FILE_NAME));
+	TEST_SUCC(rename(FILE_NAME, NEW_FILE_NAME));
+
+	TEST_RES(read_events(),
+		 _ret == 3 && event_is(0, dir_wd, IN_CREATE, "file") &&
+			 event_is(1, dir_wd, IN_MOVED_FROM, "file") &&
+			 event_is(2, dir_wd, IN_MOVED_TO, "new_file") &&
+			 events[1]->cookie != 0 &&
+			 events[1]->cookie == events[2]->cookie);
+
+	TEST_SUCC(unlink(NEW_FILE_NAME));
+	TEST_RES(read_events(),
+		 _ret == 1 && event_is(0, dir_wd, IN_DELETE, "new_file"));
+}
+END_TEST()
+
+FN_TEST(file_watch)
+{
+	int wd;
+
+	TEST_SUCC(create_file(FILE_NAME));
+	TEST_RES(read_events(),
+		 _ret == 1 && event_is(0, dir_wd, IN_CREATE, "file"));
+
+	wd = TEST_SUCC(inotify_add_watch(ifd, FILE_NAME,
+					 IN_OPEN | IN_CLOSE | IN_DELETE_SELF));
+	TEST_RES(inotify_add_watch(ifd, FILE_NAME, IN_MASK_ADD | IN_MODIFY),
+		 _ret == wd);
+
+	TEST_SUCC(open_and_close(FILE_NAME));
+	TEST_RES(read_events(),
+		 _ret == 2 && event_is(0, wd, IN_OPEN, NULL) &&
+			 event_is(1, wd, IN_CLOSE_NOWRITE, NULL));
+
+	TEST_SUCC(unlink(FILE_NAME));
+	TEST_RES(read_events(),
+		 _ret == 3 && event_is(0, wd, IN_DELETE_SELF, NULL) &&
+			 event_is(1, wd, IN_IGNORED, NULL) &&
+			 event_is(2, dir_wd, IN_DELETE, "file"));
+
+	TEST_ERRNO(inotify_rm_watch(ifd, wd), EINVAL);
+}
+END_TEST()
+
+FN_TEST(oneshot)
+{
+	int ifd2;
+	int wd;
+
+	TEST_SUCC(create_file(FILE_NAME));
+	TEST_RES(read_events(),
+		 _ret == 1 && event_is(0, dir_wd, IN_CREATE, "file"));
+
+	ifd2 = TEST_SUCC(inotify_init());
+	wd = TEST_SUCC(
+		inotify_add_watch(ifd2, FILE_NAME, IN_MODIFY | IN_ONESHOT));
+	TEST_SUCC(truncate(FILE_NAME, 10));
+	TEST_SUCC(truncate(FILE_NAME, 20));
+
+	// The watch is removed after the first event.
+	TEST_RES(read(ifd2, buf, sizeof(buf)),
+		 _ret == 2 * sizeof(struct inotify_event) &&
+			 ((struct inotify_event *)buf)[0].wd == wd &&
+			 ((struct inotify_event *)buf)[0].mask == IN_MODIFY &&
+			 ((struct inotify_event *)buf)[1].wd == wd &&
+			 ((struct inotify_event *)buf)[1].mask == IN_IGNORED);
+	TEST_ERRNO(inotify_rm_watch(ifd2, wd), EINVAL);
+	TEST_SUCC(close(ifd2));
+
+	// The identical events are merged.
+	TEST_RES(read_events(),
+		 _ret == 1 && event_is(0, dir_wd, IN_MODIFY, "file"));
+
+	TEST_SUCC(unlink(FILE_NAME));
+	TEST_RES(read_events(),
+		 _ret == 1 && event_is(0, dir_wd, IN_DELETE, "file"));
+}
+END_TEST()
+
+FN_TEST(rm_watch)
+{
+	TEST_SUCC(inotify_rm_watch(ifd, dir_wd));
+	TEST_RES(read_events(),
+		 _ret == 1 && event_is(0, dir_wd, IN_IGNORED, NULL));
+	TEST_ERRNO(inotify_rm_watch(ifd, dir_wd), EINVAL);
+
+	TEST_SUCC(mkdir(DIR_NAME "/dir", 0755));
+	TEST_SUCC(rmdir(DIR_NAME "/dir"));
+	TEST_ERRNO(read(ifd, buf, sizeof(buf)), EAGAIN);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(ifd));
+	CHECK(rmdir(DIR_NAME));
+}
+END_SETUP()
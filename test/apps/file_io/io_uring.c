@@ -0,0 +1,702 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <linux/io_uring.h>
+#include <netinet/in.h>
+#include <poll.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/socket.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/uio.h>
+#include <unistd.h>
+
+#define FILE_NAME "/tmp/io_uring_test"
+
+static int io_uring_setup(unsigned int entries, struct io_uring_params *params)
+{
+	return syscall(SYS_io_uring_setup, entries, params);
+}
+
+static int io_uring_enter(int fd, unsigned int to_submit,
+			  unsigned int min_complete, unsigned int flags)
+{
+	return syscall(SYS_io_uring_enter, fd, to_submit, min_complete, flags,
+		       NULL, 0);
+}
+
+static int io_uring_register(int fd, unsigned int opcode, void *arg,
+			     unsigned int nr_args)
+{
+	return syscall(SYS_io_uring_register, fd, opcode, arg, nr_args);
+}
+
+static int ring_fd;
+static struct io_uring_params params;
+static struct io_uring_sqe *sqes;
+static struct io_uring_cqe *cqes;
+static unsigned int *sq_tail, *sq_mask, *sq_array;
+static unsigned int *cq_head, *cq_tail, *cq_mask;
+
+static int file_fd;
+
+FN_TEST(setup_invalid)
+{
+	struct io_uring_params p;
+
+	memset(&p, 0, sizeof(p));
+	TEST_ERRNO(io_uring_setup(0, &p), EINVAL);
+	TEST_ERRNO(io_uring_setup(65536, &p), EINVAL);
+
+	p.resv[0] = 1;
+	TEST_ERRNO(io_uring_setup(4, &p), EINVAL);
+
+	memset(&p, 0, sizeof(p));
+	p.flags = IORING_SETUP_CQSIZE;
+	p.cq_entries = 2;
+	TEST_ERRNO(io_uring_setup(4, &p), EINVAL);
+}
+END_TEST()
+
+FN_TEST(setup_entries)
+{
+	struct io_uring_params p;
+	int fd;
+
+	memset(&p, 0, sizeof(p));
+	fd = TEST_RES(io_uring_setup(3, &p),
+		      p.sq_entries == 4 && p.cq_entries == 8);
+	TEST_SUCC(close(fd));
+
+	memset(&p, 0, sizeof(p));
+	p.flags = IORING_SETUP_CQSIZE;
+	p.cq_entries = 9;
+	fd = TEST_RES(io_uring_setup(4, &p),
+		      p.sq_entries == 4 && p.cq_entries == 16);
+	TEST_SUCC(close(fd));
+
+	memset(&p, 0, sizeof(p));
+	p.flags = IORING_SETUP_CLAMP;
+	fd = TEST_RES(io_uring_setup(65536, &p),
+		      p.sq_entries == 32768 && p.cq_entries == 65536);
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_SETUP(setup_ring)
+{
+	char *rings;
+	size_t rings_size;
+
+	file_fd = CHECK(open(FILE_NAME, O_RDWR | O_CREAT | O_TRUNC, 0644));
+
+	ring_fd = CHECK(io_uring_setup(4, &params));
+	CHECK_WITH(params.features, _ret & IORING_FEAT_SINGLE_MMAP);
+
+	rings_size = params.cq_off.cqes +
+		     params.cq_entries * sizeof(struct io_uring_cqe);
+	if (rings_size < params.sq_off.array +
+				 params.sq_entries * sizeof(unsigned int))
+		rings_size = params.sq_off.array +
+			     params.sq_entries * sizeof(unsigned int);
+
+	rings = (char *)CHECK_WITH((long)mmap(NULL, rings_size,
+					      PROT_READ | PROT_WRITE,
+					      MAP_SHARED | MAP_POPULATE,
+					      ring_fd, IORING_OFF_SQ_RING),
+				   _ret != (long)MAP_FAILED);
+	sqes = (struct io_uring_sqe *)CHECK_WITH(
+		(long)mmap(NULL, params.sq_entries * sizeof(struct io_uring_sqe),
+			   PROT_READ | PROT_WRITE, MAP_SHARED | MAP_POPULATE,
+			   ring_fd, IORING_OFF_SQES),
+		_ret != (long)MAP_FAILED);
+
+	sq_tail = (unsigned int *)(rings + params.sq_off.tail);
+	sq_mask = (unsigned int *)(rings + params.sq_off.ring_mask);
+	sq_array = (unsigned int *)(rings + params.sq_off.array);
+	cq_head = (unsigned int *)(rings + params.cq_off.head);
+	cq_tail = (unsigned int *)(rings + params.cq_off.tail);
+	cq_mask = (unsigned int *)(rings + params.cq_off.ring_mask);
+	cqes = (struct io_uring_cqe *)(rings + params.cq_off.cqes);
+}
+END_SETUP()
+
+static void push_sqe(const struct io_uring_sqe *sqe)
+{
+	unsigned int tail = *sq_tail;
+	unsigned int idx = tail & *sq_mask;
+
+	sqes[idx] = *sqe;
+	sq_array[idx] = idx;
+	__atomic_store_n(sq_tail, tail + 1, __ATOMIC_RELEASE);
+}
+
+static int pop_cqe(struct io_uring_cqe *cqe)
+{
+	unsigned int head = *cq_head;
+
+	if (head == __atomic_load_n(cq_tail, __ATOMIC_ACQUIRE))
+		return -1;
+
+	*cqe = cqes[head & *cq_mask];
+	__atomic_store_n(cq_head, head + 1, __ATOMIC_RELEASE);
+	return 0;
+}
+
+// Submits the SQE, waits for its completion, and returns its result and flags.
+static int run_sqe_flags(const struct io_uring_sqe *sqe, unsigned int *flags)
+{
+	struct io_uring_cqe cqe;
+
+	push_sqe(sqe);
+	if (io_uring_enter(ring_fd, 1, 1, IORING_ENTER_GETEVENTS) != 1)
+		return -1000;
+	if (pop_cqe(&cqe) < 0 || cqe.user_data != sqe->user_data)
+		return -1001;
+
+	*flags = cqe.flags;
+	return cqe.res;
+}
+
+// Submits the SQE, waits for its completion, and returns its result.
+static int run_sqe(const struct io_uring_sqe *sqe)
+{
+	unsigned int flags;
+
+	return run_sqe_flags(sqe, &flags);
+}
+
+// Waits for the next CQE without submitting SQEs.
+static int wait_cqe(struct io_uring_cqe *cqe)
+{
+	if (io_uring_enter(ring_fd, 0, 1, IORING_ENTER_GETEVENTS) < 0)
+		return -1;
+
+	return pop_cqe(cqe);
+}
+
+FN_TEST(nop)
+{
+	struct io_uring_sqe sqe = { .opcode = IORING_OP_NOP,
+				    .user_data = 42 };
+	struct io_uring_cqe cqe;
+	struct pollfd pfd = { .fd = ring_fd, .events = POLLIN | POLLOUT };
+
+	TEST_RES(run_sqe(&sqe), _ret == 0);
+
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && pfd.revents == POLLOUT);
+
+	push_sqe(&sqe);
+	TEST_RES(io_uring_enter(ring_fd, 1, 0, 0), _ret == 1);
+	TEST_RES(poll(&pfd, 1, 0),
+		 _ret == 1 && pfd.revents == (POLLIN | POLLOUT));
+	TEST_RES(pop_cqe(&cqe), cqe.user_data == 42 && cqe.res == 0);
+	TEST_RES(pop_cqe(&cqe), _ret < 0);
+}
+END_TEST()
+
+FN_TEST(read_write)
+{
+	struct iovec iovs[2] = {
+		{ .iov_base = "hello", .iov_len = 5 },
+		{ .iov_base = "world", .iov_len = 5 },
+	};
+	struct io_uring_sqe sqe;
+	char *buf;
+
+	// The buffer is not populated, so the page faults must be handled.
+	buf = (char *)TEST_SUCC((long)mmap(NULL, 4096, PROT_READ | PROT_WRITE,
+					   MAP_PRIVATE | MAP_ANONYMOUS, -1, 0));
+
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = IORING_OP_WRITEV;
+	sqe.fd = file_fd;
+	sqe.addr = (unsigned long)iovs;
+	sqe.len = 2;
+	sqe.off = 0;
+	TEST_RES(run_sqe(&sqe), _ret == 10);
+
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = IORING_OP_READ;
+	sqe.fd = file_fd;
+	sqe.addr = (unsigned long)buf;
+	sqe.len = 4096;
+	sqe.off = 3;
+	TEST_RES(run_sqe(&sqe), _ret == 7 && memcmp(buf, "loworld", 7) == 0);
+
+	// The offset `-1` means the current file position.
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = IORING_OP_WRITE;
+	sqe.fd = file_fd;
+	sqe.addr = (unsigned long)"abc";
+	sqe.len = 3;
+	sqe.off = -1;
+	TEST_RES(run_sqe(&sqe), _ret == 3);
+	TEST_RES(lseek(file_fd, 0, SEEK_CUR), _ret == 3);
+
+	iovs[0].iov_base = buf;
+	iovs[0].iov_len = 2;
+	iovs[1].iov_base = buf + 2048;
+	iovs[1].iov_len = 2048;
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = IORING_OP_READV;
+	sqe.fd = file_fd;
+	sqe.addr = (unsigned long)iovs;
+	sqe.len = 2;
+	sqe.off = -1;
+	TEST_RES(run_sqe(&sqe), _ret == 7 && memcmp(buf, "lo", 2) == 0 &&
+					memcmp(buf + 2048, "world", 5) == 0);
+	TEST_RES(lseek(file_fd, 0, SEEK_CUR), _ret == 10);
+
+	TEST_SUCC(munmap(buf, 4096));
+}
+END_TEST()
+
+FN_TEST(fsync)
+{
+	struct io_uring_sqe sqe;
+
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = IORING_OP_FSYNC;
+	sqe.fd = file_fd;
+	TEST_RES(run_sqe(&sqe), _ret == 0);
+
+	sqe.fsync_flags = IORING_FSYNC_DATASYNC;
+	TEST_RES(run_sqe(&sqe), _ret == 0);
+
+	sqe.fsync_flags = 0x80000000;
+	TEST_RES(run_sqe(&sqe), _ret == -EINVAL);
+}
+END_TEST()
+
+FN_TEST(accept)
+{
+	struct sockaddr_in addr = { .sin_family = AF_INET,
+				    .sin_addr = { htonl(INADDR_LOOPBACK) } };
+	struct sockaddr_in peer_addr;
+	socklen_t addrlen = sizeof(addr);
+	struct io_uring_sqe sqe;
+	struct io_uring_cqe cqe;
+	int listen_fd, client_fd;
+
+	listen_fd = TEST_SUCC(socket(AF_INET, SOCK_STREAM, 0));
+	TEST_SUCC(bind(listen_fd, (struct sockaddr *)&addr, sizeof(addr)));
+	TEST_SUCC(listen(listen_fd, 1));
+	TEST_SUCC(getsockname(listen_fd, (struct sockaddr *)&addr, &addrlen));
+
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = IORING_OP_ACCEPT;
+	sqe.fd = listen_fd;
+	sqe.addr = (unsigned long)&peer_addr;
+	sqe.off = (unsigned long)&addrlen;
+	sqe.accept_flags = SOCK_CLOEXEC;
+	sqe.user_data = 1;
+
+	// The request blocks until the connection is established.
+	push_sqe(&sqe);
+	TEST_RES(io_uring_enter(ring_fd, 1, 0, 0), _ret == 1);
+
+	client_fd = TEST_SUCC(socket(AF_INET, SOCK_STREAM, 0));
+	TEST_SUCC(connect(client_fd, (struct sockaddr *)&addr, sizeof(addr)));
+
+	TEST_RES(io_uring_enter(ring_fd, 0, 1, IORING_ENTER_GETEVENTS),
+		 _ret == 0);
+	TEST_RES(pop_cqe(&cqe), cqe.user_data == 1 && cqe.res >= 0 &&
+					addrlen == sizeof(peer_addr) &&
+					peer_addr.sin_family == AF_INET);
+	TEST_RES(fcntl(cqe.res, F_GETFD), _ret == FD_CLOEXEC);
+
+	TEST_SUCC(close(cqe.res));
+	TEST_SUCC(close(client_fd));
+	TEST_SUCC(close(listen_fd));
+}
+END_TEST()
+
+FN_TEST(accept_multishot)
+{
+	struct sockaddr_in addr = { .sin_family = AF_INET,
+				    .sin_addr = { htonl(INADDR_LOOPBACK) } };
+	socklen_t addrlen = sizeof(addr);
+	struct io_uring_sqe sqe;
+	struct io_uring_cqe cqe;
+	int listen_fd, client_fds[2], i;
+
+	listen_fd = TEST_SUCC(socket(AF_INET, SOCK_STREAM, 0));
+	TEST_SUCC(bind(listen_fd, (struct sockaddr *)&addr, sizeof(addr)));
+	TEST_SUCC(listen(listen_fd, 2));
+	TEST_SUCC(getsockname(listen_fd, (struct sockaddr *)&addr, &addrlen));
+
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = IORING_OP_ACCEPT;
+	sqe.fd = listen_fd;
+	sqe.ioprio = IORING_ACCEPT_MULTISHOT;
+	sqe.user_data = 2;
+	push_sqe(&sqe);
+	TEST_RES(io_uring_enter(ring_fd, 1, 0, 0), _ret == 1);
+
+	for (i = 0; i < 2; i++) {
+		client_fds[i] = TEST_SUCC(socket(AF_INET, SOCK_STREAM, 0));
+		TEST_SUCC(connect(client_fds[i], (struct sockaddr *)&addr,
+				  sizeof(addr)));
+		TEST_RES(wait_cqe(&cqe),
+			 cqe.user_data == 2 && cqe.res >= 0 &&
+				 cqe.flags == IORING_CQE_F_MORE);
+		TEST_SUCC(close(cqe.res));
+	}
+
+	// The request ends when the socket stops listening.
+	TEST_SUCC(shutdown(listen_fd, SHUT_RD));
+	TEST_RES(wait_cqe(&cqe), cqe.user_data == 2 && cqe.res < 0 &&
+					 !(cqe.flags & IORING_CQE_F_MORE));
+
+	TEST_SUCC(close(client_fds[0]));
+	TEST_SUCC(close(client_fds[1]));
+	TEST_SUCC(close(listen_fd));
+}
+END_TEST()
+
+FN_TEST(openat_statx)
+{
+	struct io_uring_sqe sqe;
+	struct statx stx;
+	char buf[16];
+	int fd;
+
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = IORING_OP_OPENAT;
+	sqe.fd = AT_FDCWD;
+	sqe.addr = (unsigned long)FILE_NAME;
+	sqe.open_flags = O_RDONLY | O_CLOEXEC;
+	fd = TEST_RES(run_sqe(&sqe), _ret >= 0);
+	TEST_RES(fcntl(fd, F_GETFD), _ret == FD_CLOEXEC);
+	TEST_RES(read(fd, buf, sizeof(buf)),
+		 _ret == 10 && memcmp(buf, "abcloworld", 10) == 0);
+	TEST_SUCC(close(fd));
+
+	sqe.addr = (unsigned long)"/tmp/io_uring_nonexistent";
+	TEST_RES(run_sqe(&sqe), _ret == -ENOENT);
+
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = IORING_OP_STATX;
+	sqe.fd = AT_FDCWD;
+	sqe.addr = (unsigned long)FILE_NAME;
+	sqe.len = STATX_BASIC_STATS;
+	sqe.off = (unsigned long)&stx;
+	TEST_RES(run_sqe(&sqe),
+		 _ret == 0 && stx.stx_size == 10 && S_ISREG(stx.stx_mode));
+
+	sqe.addr = (unsigned long)"/tmp/io_uring_nonexistent";
+	TEST_RES(run_sqe(&sqe), _ret == -ENOENT);
+}
+END_TEST()
+
+FN_TEST(splice)
+{
+	struct io_uring_sqe sqe;
+	char buf[16];
+	int fds[2];
+
+	TEST_SUCC(pipe(fds));
+
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = IORING_OP_SPLICE;
+	sqe.splice_fd_in = file_fd;
+	sqe.splice_off_in = 3;
+	sqe.fd = fds[1];
+	sqe.off = -1;
+	sqe.len = 4;
+	TEST_RES(run_sqe(&sqe), _ret == 4);
+	TEST_RES(read(fds[0], buf, sizeof(buf)),
+		 _ret == 4 && memcmp(buf, "lowo", 4) == 0);
+
+	TEST_SUCC(write(fds[1], "xyz", 3));
+	sqe.splice_fd_in = fds[0];
+	sqe.splice_off_in = -1;
+	sqe.fd = file_fd;
+	sqe.off = 10;
+	sqe.len = sizeof(buf);
+	TEST_RES(run_sqe(&sqe), _ret == 3);
+	TEST_RES(pread(file_fd, buf, sizeof(buf), 7),
+		 _ret == 6 && memcmp(buf, "rldxyz", 6) == 0);
+
+	// One of the files must be a pipe, whose offset must be -1.
+	sqe.splice_fd_in = file_fd;
+	sqe.splice_off_in = 0;
+	TEST_RES(run_sqe(&sqe), _ret == -EINVAL);
+	sqe.splice_fd_in = fds[0];
+	TEST_RES(run_sqe(&sqe), _ret == -ESPIPE);
+
+	TEST_SUCC(close(fds[0]));
+	TEST_SUCC(close(fds[1]));
+}
+END_TEST()
+
+FN_TEST(provide_buffers)
+{
+	struct io_uring_sqe sqe, recv_sqe;
+	unsigned int flags;
+	char bufs[3][8];
+	int fds[2];
+
+	TEST_SUCC(socketpair(AF_UNIX, SOCK_STREAM, 0, fds));
+
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = IORING_OP_PROVIDE_BUFFERS;
+	sqe.fd = 3;
+	sqe.addr = (unsigned long)bufs;
+	sqe.len = sizeof(bufs[0]);
+	sqe.off = 10;
+	sqe.buf_group = 1;
+	TEST_RES(run_sqe(&sqe), _ret == 0);
+
+	memset(&recv_sqe, 0, sizeof(recv_sqe));
+	recv_sqe.opcode = IORING_OP_RECV;
+	recv_sqe.fd = fds[0];
+	recv_sqe.flags = IOSQE_BUFFER_SELECT;
+	recv_sqe.buf_group = 1;
+
+	// The buffers are selected in order.
+	TEST_SUCC(write(fds[1], "hello", 5));
+	TEST_RES(run_sqe_flags(&recv_sqe, &flags),
+		 _ret == 5 &&
+			 flags == (IORING_CQE_F_BUFFER |
+				   10 << IORING_CQE_BUFFER_SHIFT) &&
+			 memcmp(bufs[0], "hello", 5) == 0);
+	TEST_SUCC(write(fds[1], "world", 5));
+	TEST_RES(run_sqe_flags(&recv_sqe, &flags),
+		 _ret == 5 &&
+			 flags == (IORING_CQE_F_BUFFER |
+				   11 << IORING_CQE_BUFFER_SHIFT) &&
+			 memcmp(bufs[1], "world", 5) == 0);
+
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = IORING_OP_REMOVE_BUFFERS;
+	sqe.fd = 8;
+	sqe.buf_group = 1;
+	TEST_RES(run_sqe(&sqe), _ret == 1);
+	TEST_RES(run_sqe(&sqe), _ret == 0);
+	sqe.buf_group = 2;
+	TEST_RES(run_sqe(&sqe), _ret == -ENOENT);
+
+	TEST_SUCC(write(fds[1], "abc", 3));
+	TEST_RES(run_sqe(&recv_sqe), _ret == -ENOBUFS);
+
+	// Only some requests can select buffers.
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = IORING_OP_READ;
+	sqe.fd = fds[0];
+	sqe.flags = IOSQE_BUFFER_SELECT;
+	sqe.buf_group = 1;
+	TEST_RES(run_sqe(&sqe), _ret == -EINVAL);
+
+	TEST_SUCC(close(fds[0]));
+	TEST_SUCC(close(fds[1]));
+}
+END_TEST()
+
+FN_TEST(recv_multishot)
+{
+	struct io_uring_buf_reg reg;
+	struct io_uring_buf_ring *br;
+	struct io_uring_sqe sqe;
+	struct io_uring_cqe cqe;
+	char bufs[2][8];
+	int fds[2], i;
+
+	TEST_SUCC(socketpair(AF_UNIX, SOCK_STREAM, 0, fds));
+
+	br = (struct io_uring_buf_ring *)TEST_RES(
+		(long)mmap(NULL, 4096, PROT_READ | PROT_WRITE,
+			   MAP_PRIVATE | MAP_ANONYMOUS, -1, 0),
+		_ret != (long)MAP_FAILED);
+	for (i = 0; i < 2; i++) {
+		br->bufs[i].addr = (unsigned long)bufs[i];
+		br->bufs[i].len = sizeof(bufs[i]);
+		br->bufs[i].bid = i;
+	}
+	__atomic_store_n(&br->tail, 2, __ATOMIC_RELEASE);
+
+	memset(&reg, 0, sizeof(reg));
+	reg.ring_addr = (unsigned long)br;
+	reg.ring_entries = 4;
+	reg.bgid = 3;
+	TEST_SUCC(io_uring_register(ring_fd, IORING_REGISTER_PBUF_RING, &reg,
+				    1));
+	TEST_ERRNO(io_uring_register(ring_fd, IORING_REGISTER_PBUF_RING, &reg,
+				     1),
+		   EEXIST);
+
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = IORING_OP_RECV;
+	sqe.fd = fds[0];
+	sqe.flags = IOSQE_BUFFER_SELECT;
+	sqe.buf_group = 3;
+	sqe.ioprio = IORING_RECV_MULTISHOT;
+	sqe.user_data = 3;
+	push_sqe(&sqe);
+	TEST_RES(io_uring_enter(ring_fd, 1, 0, 0), _ret == 1);
+
+	TEST_SUCC(write(fds[1], "abc", 3));
+	TEST_RES(wait_cqe(&cqe),
+		 cqe.user_data == 3 && cqe.res == 3 &&
+			 cqe.flags ==
+				 (IORING_CQE_F_BUFFER | IORING_CQE_F_MORE) &&
+			 memcmp(bufs[0], "abc", 3) == 0);
+	TEST_SUCC(write(fds[1], "defg", 4));
+	TEST_RES(wait_cqe(&cqe),
+		 cqe.user_data == 3 && cqe.res == 4 &&
+			 cqe.flags == (IORING_CQE_F_BUFFER | IORING_CQE_F_MORE |
+				       1 << IORING_CQE_BUFFER_SHIFT) &&
+			 memcmp(bufs[1], "defg", 4) == 0);
+
+	// The request ends at the end of the stream.
+	TEST_SUCC(close(fds[1]));
+	TEST_RES(wait_cqe(&cqe), cqe.user_data == 3 && cqe.res == 0 &&
+					 !(cqe.flags & IORING_CQE_F_MORE));
+
+	TEST_SUCC(io_uring_register(ring_fd, IORING_UNREGISTER_PBUF_RING, &reg,
+				    1));
+	TEST_ERRNO(io_uring_register(ring_fd, IORING_UNREGISTER_PBUF_RING,
+				     &reg, 1),
+		   ENOENT);
+
+	TEST_SUCC(close(fds[0]));
+	TEST_SUCC(munmap(br, 4096));
+}
+END_TEST()
+
+FN_TEST(invalid_requests)
+{
+	struct io_uring_sqe sqe;
+
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = 0xff;
+	TEST_RES(run_sqe(&sqe), _ret == -EINVAL);
+
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = IORING_OP_READ;
+	sqe.fd = -1;
+	TEST_RES(run_sqe(&sqe), _ret == -EBADF);
+
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = IORING_OP_WRITE;
+	sqe.fd = file_fd;
+	sqe.off = -2;
+	TEST_RES(run_sqe(&sqe), _ret == -EINVAL);
+}
+END_TEST()
+
+FN_TEST(invalid_fds)
+{
+	TEST_ERRNO(io_uring_enter(file_fd, 0, 0, 0), EOPNOTSUPP);
+	TEST_ERRNO(io_uring_enter(-1, 0, 0, 0), EBADF);
+}
+END_TEST()
+
+FN_TEST(probe)
+{
+	char buf[sizeof(struct io_uring_probe) +
+		 256 * sizeof(struct io_uring_probe_op)];
+	struct io_uring_probe *probe = (struct io_uring_probe *)buf;
+
+	memset(buf, 0, sizeof(buf));
+	TEST_RES(io_uring_register(ring_fd, IORING_REGISTER_PROBE, probe, 256),
+		 probe->last_op >= IORING_OP_REMOVE_BUFFERS &&
+			 probe->ops_len > IORING_OP_REMOVE_BUFFERS &&
+			 (probe->ops[IORING_OP_READV].flags &
+			  IO_URING_OP_SUPPORTED) &&
+			 (probe->ops[IORING_OP_ACCEPT].flags &
+			  IO_URING_OP_SUPPORTED) &&
+			 (probe->ops[IORING_OP_SPLICE].flags &
+			  IO_URING_OP_SUPPORTED));
+
+	// The probe must be zeroed.
+	TEST_ERRNO(io_uring_register(ring_fd, IORING_REGISTER_PROBE, probe,
+				     256),
+		   EINVAL);
+}
+END_TEST()
+
+FN_TEST(blocking_requests)
+{
+	struct io_uring_sqe sqe;
+	struct io_uring_cqe cqe;
+	char bufs[20], buf[16];
+	int fds[2], i;
+
+	TEST_SUCC(pipe(fds));
+
+	// The blocking requests must not prevent other requests from running.
+	for (i = 0; i < sizeof(bufs); i++) {
+		memset(&sqe, 0, sizeof(sqe));
+		sqe.opcode = IORING_OP_READ;
+		sqe.fd = fds[0];
+		sqe.addr = (unsigned long)&bufs[i];
+		sqe.len = 1;
+		sqe.off = -1;
+		sqe.user_data = 100 + i;
+		push_sqe(&sqe);
+		TEST_RES(io_uring_enter(ring_fd, 1, 0, 0), _ret == 1);
+	}
+
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = IORING_OP_READ;
+	sqe.fd = file_fd;
+	sqe.addr = (unsigned long)buf;
+	sqe.len = sizeof(buf);
+	sqe.off = 0;
+	sqe.user_data = 99;
+	TEST_RES(run_sqe(&sqe), _ret > 0);
+
+	TEST_RES(write(fds[1], "abcdefghijklmnopqrst", sizeof(bufs)),
+		 _ret == sizeof(bufs));
+	for (i = 0; i < sizeof(bufs); i++)
+		TEST_RES(wait_cqe(&cqe),
+			 cqe.user_data >= 100 &&
+				 cqe.user_data < 100 + sizeof(bufs) &&
+				 cqe.res == 1);
+
+	TEST_SUCC(close(fds[0]));
+	TEST_SUCC(close(fds[1]));
+}
+END_TEST()
+
+FN_TEST(close_cancels)
+{
+	struct io_uring_sqe sqe;
+	char buf;
+	int fds[2];
+
+	TEST_SUCC(pipe(fds));
+
+	memset(&sqe, 0, sizeof(sqe));
+	sqe.opcode = IORING_OP_READ;
+	sqe.fd = fds[0];
+	sqe.addr = (unsigned long)&buf;
+	sqe.len = 1;
+	sqe.off = -1;
+	push_sqe(&sqe);
+	TEST_RES(io_uring_enter(ring_fd, 1, 0, 0), _ret == 1);
+
+	// The pending request is canceled, so it does not consume the data.
+	TEST_SUCC(close(ring_fd));
+	TEST_RES(write(fds[1], "x", 1), _ret == 1);
+	TEST_SUCC(fcntl(fds[0], F_SETFL, O_NONBLOCK));
+	TEST_RES(read(fds[0], &buf, 1), _ret == 1 && buf == 'x');
+
+	TEST_SUCC(close(fds[0]));
+	TEST_SUCC(close(fds[1]));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(file_fd));
+	CHECK(unlink(FILE_NAME));
+}
+END_SETUP()
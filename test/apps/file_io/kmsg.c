@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <poll.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static int rfd, wfd;
+
+FN_SETUP(open_kmsg)
+{
+	rfd = CHECK(open("/dev/kmsg", O_RDONLY | O_NONBLOCK));
+	wfd = CHECK(open("/dev/kmsg", O_WRONLY));
+}
+END_SETUP()
+
+static unsigned int priority;
+static unsigned long long seq;
+static char text[1024];
+
+// Reads the next record written by this test and parses its fields.
+//
+// The records of the kernel (e.g., the warnings of the missing system calls) are skipped.
+static int read_record(void)
+{
+	static char buf[2048];
+	unsigned long long timestamp;
+	ssize_t len;
+	int pos;
+
+	do {
+		len = read(rfd, buf, sizeof(buf) - 1);
+		if (len < 0)
+			return -1;
+		buf[len] = '\0';
+
+		if (sscanf(buf, "%u,%llu,%llu,-;%n", &priority, &seq,
+			   &timestamp, &pos) != 3 ||
+		    buf[len - 1] != '\n')
+			return -1;
+		buf[len - 1] = '\0';
+	} while (strncmp(buf + pos, "kmsg", 4) != 0);
+
+	strcpy(text, buf + pos);
+	return 0;
+}
+
+FN_TEST(seek)
+{
+	TEST_ERRNO(lseek(rfd, 1, SEEK_SET), ESPIPE);
+	TEST_ERRNO(lseek(rfd, 0, SEEK_CUR), EINVAL);
+	TEST_RES(lseek(rfd, 0, SEEK_SET), _ret == 0);
+	TEST_RES(lseek(rfd, 0, SEEK_DATA), _ret == 0);
+	TEST_RES(lseek(rfd, 0, SEEK_END), _ret == 0);
+
+	// There are no new records after seeking to the end.
+	TEST_ERRNO(read_record(), EAGAIN);
+}
+END_TEST()
+
+FN_TEST(read_records)
+{
+	unsigned long long first_seq;
+
+	TEST_RES(write(wfd, "<5>kmsg test: first\n", 20), _ret == 20);
+	TEST_RES(write(wfd, "kmsg test: second", 17), _ret == 17);
+
+	// The facility defaults to `LOG_USER`, and the level defaults to `LOG_WARNING`.
+	TEST_RES(read_record(),
+		 priority == 13 && strcmp(text, "kmsg test: first") == 0);
+	first_seq = seq;
+	TEST_RES(read_record(), priority == 12 && seq > first_seq &&
+					strcmp(text, "kmsg test: second") == 0);
+	TEST_ERRNO(read_record(), EAGAIN);
+}
+END_TEST()
+
+FN_TEST(escape_text)
+{
+	TEST_RES(write(wfd, "<14>kmsg\ttest\\", 14), _ret == 14);
+	TEST_RES(read_record(), priority == 14 &&
+					strcmp(text, "kmsg\\x09test\\x5c") == 0);
+}
+END_TEST()
+
+FN_TEST(small_buffer)
+{
+	char buf[8];
+
+	TEST_RES(write(wfd, "kmsg test: small buffer", 23), _ret == 23);
+	TEST_ERRNO(read(rfd, buf, sizeof(buf)), EINVAL);
+
+	// The record can still be read with a larger buffer.
+	TEST_RES(read_record(), strcmp(text, "kmsg test: small buffer") == 0);
+}
+END_TEST()
+
+FN_TEST(poll_records)
+{
+	struct pollfd pfd = { .fd = rfd, .events = POLLIN };
+
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+	TEST_RES(write(wfd, "kmsg test: poll", 15), _ret == 15);
+	TEST_RES(poll(&pfd, 1, 1000), _ret == 1 && pfd.revents == POLLIN);
+	TEST_RES(read_record(), strcmp(text, "kmsg test: poll") == 0);
+}
+END_TEST()
+
+FN_TEST(blocking_read)
+{
+	int status;
+	pid_t pid;
+
+	TEST_SUCC(fcntl(rfd, F_SETFL, 0));
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		usleep(100 * 1000);
+		CHECK_WITH(write(wfd, "kmsg test: blocking", 19), _ret == 19);
+		exit(EXIT_SUCCESS);
+	}
+
+	TEST_RES(read_record(), strcmp(text, "kmsg test: blocking") == 0);
+	TEST_RES(wait(&status), _ret == pid && WIFEXITED(status) &&
+					WEXITSTATUS(status) == 0);
+
+	TEST_SUCC(fcntl(rfd, F_SETFL, O_NONBLOCK));
+}
+END_TEST()
+
+FN_SETUP(close_kmsg)
+{
+	CHECK(close(rfd));
+	CHECK(close(wfd));
+}
+END_SETUP()
@@ -0,0 +1,110 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <limits.h>
+#include <string.h>
+#include <sys/uio.h>
+#include <unistd.h>
+
+#ifndef RWF_HIPRI
+#define RWF_HIPRI 0x00000001
+#endif
+#ifndef RWF_DSYNC
+#define RWF_DSYNC 0x00000002
+#endif
+#ifndef RWF_SYNC
+#define RWF_SYNC 0x00000004
+#endif
+#ifndef RWF_NOWAIT
+#define RWF_NOWAIT 0x00000008
+#endif
+
+#define FILE_NAME "/tmp/rwf_flags_test"
+
+static int rfd, wfd;
+static int file_fd;
+
+FN_SETUP(open_files)
+{
+	int fildes[2];
+
+	CHECK(pipe(fildes));
+	rfd = fildes[0];
+	wfd = fildes[1];
+
+	file_fd = CHECK(open(FILE_NAME, O_RDWR | O_CREAT | O_TRUNC, 0644));
+}
+END_SETUP()
+
+FN_TEST(unsupported_flags)
+{
+	char buf[4];
+	struct iovec iov = { .iov_base = buf, .iov_len = sizeof(buf) };
+
+	TEST_ERRNO(preadv2(file_fd, &iov, 1, 0, 0x80000000), EOPNOTSUPP);
+	TEST_ERRNO(pwritev2(file_fd, &iov, 1, 0, 0x80000000), EOPNOTSUPP);
+}
+END_TEST()
+
+FN_TEST(nowait_pipe)
+{
+	char buf[8];
+	struct iovec iov = { .iov_base = buf, .iov_len = sizeof(buf) };
+
+	// The pipe is empty, so reading from it would block.
+	TEST_ERRNO(preadv2(rfd, &iov, 1, -1, RWF_NOWAIT), EAGAIN);
+
+	TEST_RES(write(wfd, "hello", 5), _ret == 5);
+	TEST_RES(preadv2(rfd, &iov, 1, -1, RWF_NOWAIT),
+		 _ret == 5 && memcmp(buf, "hello", 5) == 0);
+
+	// The flag only takes effect for a single call.
+	TEST_RES(fcntl(rfd, F_GETFL), (_ret & O_NONBLOCK) == 0);
+}
+END_TEST()
+
+FN_TEST(sync_file)
+{
+	char buf[8];
+	struct iovec iovs[2] = {
+		{ .iov_base = "abc", .iov_len = 3 },
+		{ .iov_base = "de", .iov_len = 2 },
+	};
+	struct iovec iov = { .iov_base = buf, .iov_len = sizeof(buf) };
+
+	TEST_RES(pwritev2(file_fd, iovs, 2, 0, RWF_DSYNC | RWF_HIPRI),
+		 _ret == 5);
+	TEST_RES(pwritev2(file_fd, iovs, 1, 5, RWF_SYNC), _ret == 3);
+	TEST_RES(preadv2(file_fd, &iov, 1, 0, RWF_HIPRI | RWF_NOWAIT),
+		 _ret == 8 && memcmp(buf, "abcdeabc", 8) == 0);
+}
+END_TEST()
+
+FN_TEST(iov_max)
+{
+	static struct iovec iovs[IOV_MAX + 1];
+	char buf[1];
+
+	for (int i = 0; i < IOV_MAX + 1; i++) {
+		iovs[i].iov_base = buf;
+		iovs[i].iov_len = 0;
+	}
+
+	TEST_RES(preadv(file_fd, iovs, IOV_MAX, 0), _ret == 0);
+	TEST_ERRNO(preadv(file_fd, iovs, IOV_MAX + 1, 0), EINVAL);
+	TEST_ERRNO(writev(wfd, iovs, IOV_MAX + 1), EINVAL);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(rfd));
+	CHECK(close(wfd));
+	CHECK(close(file_fd));
+	CHECK(unlink(FILE_NAME));
+}
+END_SETUP()
@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <string.h>
+#include <sys/klog.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define SYSLOG_ACTION_CLOSE 0
+#define SYSLOG_ACTION_OPEN 1
+#define SYSLOG_ACTION_READ 2
+#define SYSLOG_ACTION_READ_ALL 3
+#define SYSLOG_ACTION_READ_CLEAR 4
+#define SYSLOG_ACTION_CLEAR 5
+#define SYSLOG_ACTION_CONSOLE_OFF 6
+#define SYSLOG_ACTION_CONSOLE_ON 7
+#define SYSLOG_ACTION_CONSOLE_LEVEL 8
+#define SYSLOG_ACTION_SIZE_UNREAD 9
+#define SYSLOG_ACTION_SIZE_BUFFER 10
+
+static int wfd;
+static char buf[128 * 1024];
+
+FN_SETUP(open_kmsg)
+{
+	wfd = CHECK(open("/dev/kmsg", O_WRONLY));
+}
+END_SETUP()
+
+// Reads all the records and returns whether the line is in the kernel log
+// with the priority.
+static int find_line(const char *prefix, const char *text)
+{
+	char *pos = buf;
+	int len;
+
+	len = klogctl(SYSLOG_ACTION_READ_ALL, buf, sizeof(buf) - 1);
+	if (len < 0)
+		return -1;
+	buf[len] = '\0';
+
+	while ((pos = strstr(pos, text)) != NULL) {
+		if (pos - buf >= 2 && pos[-1] == ' ' && pos[-2] == ']' &&
+		    pos[strlen(text)] == '\n') {
+			while (pos > buf && pos[-1] != '\n')
+				pos--;
+			return strncmp(pos, prefix, strlen(prefix)) == 0;
+		}
+		pos++;
+	}
+	return 0;
+}
+
+FN_TEST(invalid_args)
+{
+	TEST_ERRNO(klogctl(-1, buf, sizeof(buf)), EINVAL);
+	TEST_ERRNO(klogctl(11, buf, sizeof(buf)), EINVAL);
+
+	TEST_ERRNO(klogctl(SYSLOG_ACTION_READ_ALL, NULL, 1), EINVAL);
+	TEST_ERRNO(klogctl(SYSLOG_ACTION_READ_ALL, buf, -1), EINVAL);
+	TEST_RES(klogctl(SYSLOG_ACTION_READ_ALL, buf, 0), _ret == 0);
+	TEST_ERRNO(klogctl(SYSLOG_ACTION_READ, NULL, 1), EINVAL);
+	TEST_RES(klogctl(SYSLOG_ACTION_READ, buf, 0), _ret == 0);
+
+	TEST_ERRNO(klogctl(SYSLOG_ACTION_CONSOLE_LEVEL, NULL, 0), EINVAL);
+	TEST_ERRNO(klogctl(SYSLOG_ACTION_CONSOLE_LEVEL, NULL, 9), EINVAL);
+}
+END_TEST()
+
+FN_TEST(open_and_close)
+{
+	TEST_RES(klogctl(SYSLOG_ACTION_OPEN, NULL, 0), _ret == 0);
+	TEST_RES(klogctl(SYSLOG_ACTION_CLOSE, NULL, 0), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(size_buffer)
+{
+	TEST_RES(klogctl(SYSLOG_ACTION_SIZE_BUFFER, NULL, 0), _ret > 0);
+}
+END_TEST()
+
+FN_TEST(read_all)
+{
+	TEST_RES(write(wfd, "<5>syslog test: read all\n", 25), _ret == 25);
+	TEST_RES(find_line("<13>[", "syslog test: read all"), _ret == 1);
+
+	// The records are not consumed.
+	TEST_RES(find_line("<13>[", "syslog test: read all"), _ret == 1);
+}
+END_TEST()
+
+FN_TEST(clear)
+{
+	TEST_RES(write(wfd, "syslog test: before clear\n", 26), _ret == 26);
+	TEST_RES(klogctl(SYSLOG_ACTION_READ_CLEAR, buf, sizeof(buf)),
+		 _ret > 0);
+	TEST_RES(find_line("<12>[", "syslog test: before clear"), _ret == 0);
+
+	TEST_RES(write(wfd, "syslog test: after clear\n", 25), _ret == 25);
+	TEST_RES(klogctl(SYSLOG_ACTION_CLEAR, NULL, 0), _ret == 0);
+	TEST_RES(find_line("<12>[", "syslog test: after clear"), _ret == 0);
+}
+END_TEST()
+
+// Returns whether the last line in the buffer is the text with the priority.
+static int is_last_line(int len, const char *prefix, const char *text)
+{
+	char *pos;
+
+	if (len <= 0 || buf[len - 1] != '\n')
+		return 0;
+	buf[len - 1] = '\0';
+
+	pos = strrchr(buf, '\n');
+	pos = pos ? pos + 1 : buf;
+	if (strncmp(pos, prefix, strlen(prefix)) != 0)
+		return 0;
+
+	pos = strstr(pos, "] ");
+	return pos && strcmp(pos + 2, text) == 0;
+}
+
+// Reads the records until the last one read is the text with the priority.
+static int read_until(const char *prefix, const char *text)
+{
+	int len;
+
+	// The unread records are checked first, so the read never blocks.
+	while (klogctl(SYSLOG_ACTION_SIZE_UNREAD, NULL, 0) > 0) {
+		len = klogctl(SYSLOG_ACTION_READ, buf, sizeof(buf) - 1);
+		if (len < 0)
+			return -1;
+		if (is_last_line(len, prefix, text))
+			return 1;
+	}
+	return 0;
+}
+
+FN_TEST(read)
+{
+	TEST_RES(write(wfd, "<6>syslog test: read\n", 21), _ret == 21);
+	TEST_RES(klogctl(SYSLOG_ACTION_SIZE_UNREAD, NULL, 0), _ret > 0);
+
+	// The records are consumed.
+	TEST_RES(read_until("<14>[", "syslog test: read"), _ret == 1);
+	TEST_RES(klogctl(SYSLOG_ACTION_SIZE_UNREAD, NULL, 0), _ret == 0);
+}
+END_TEST()
+
+static void drop_privileges_and_clear(void)
+{
+	CHECK(setresuid(65534, 65534, 65534));
+
+	CHECK_WITH(klogctl(SYSLOG_ACTION_CLEAR, NULL, 0),
+		   _ret < 0 && errno == EPERM);
+	CHECK_WITH(klogctl(SYSLOG_ACTION_SIZE_UNREAD, NULL, 0),
+		   _ret < 0 && errno == EPERM);
+	CHECK_WITH(klogctl(SYSLOG_ACTION_CONSOLE_OFF, NULL, 0),
+		   _ret < 0 && errno == EPERM);
+}
+
+FN_TEST(unprivileged)
+{
+	int status;
+	pid_t pid;
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		drop_privileges_and_clear();
+		exit(EXIT_SUCCESS);
+	}
+
+	TEST_RES(wait(&status), _ret == pid && WIFEXITED(status) &&
+					WEXITSTATUS(status) == 0);
+}
+END_TEST()
+
+FN_TEST(console_off_and_on)
+{
+	// Turning the console off and on restores the console log level.
+	TEST_RES(klogctl(SYSLOG_ACTION_CONSOLE_OFF, NULL, 0), _ret == 0);
+	TEST_RES(klogctl(SYSLOG_ACTION_CONSOLE_OFF, NULL, 0), _ret == 0);
+	TEST_RES(klogctl(SYSLOG_ACTION_CONSOLE_ON, NULL, 0), _ret == 0);
+	TEST_RES(klogctl(SYSLOG_ACTION_CONSOLE_ON, NULL, 0), _ret == 0);
+}
+END_TEST()
+
+FN_SETUP(close_kmsg)
+{
+	CHECK(close(wfd));
+}
+END_SETUP()
@@ -0,0 +1,82 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// The executor of the netlink packets generated by `cargo osdk fuzz`.
+//
+// The input is a sequence of packets, with all the integers in little endian:
+//
+//   packet := flags:u8 len:u16 data:u8{len}
+//
+// Each packet is sent to the kernel over a `NETLINK_ROUTE` socket, and the
+// replies are drained before the next packet is sent. If bit 0 of the flags
+// is set and the packet is long enough, the length in the first netlink
+// header is set to the length of the packet, so that the mutated packets
+// are not all rejected by the length check.
+//
+// See `osdk/src/commands/fuzz/input.rs` for the generator.
+
+#include <fcntl.h>
+#include <linux/netlink.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+#define MAX_INPUT (64 * 1024)
+#define FLAG_FIX_LEN 0x1
+
+static unsigned char input[MAX_INPUT];
+static unsigned char reply[64 * 1024];
+
+int main(int argc, char *argv[])
+{
+	struct sockaddr_nl kernel_addr = { .nl_family = AF_NETLINK };
+	size_t input_len = 0, pos = 0, len;
+	unsigned char flags;
+	uint32_t nlmsg_len;
+	ssize_t n;
+	int fd, sk;
+
+	if (argc != 2) {
+		fprintf(stderr, "usage: %s <PACKETS>\n", argv[0]);
+		return EXIT_FAILURE;
+	}
+
+	fd = open(argv[1], O_RDONLY);
+	if (fd < 0) {
+		perror("open");
+		return EXIT_FAILURE;
+	}
+	while ((n = read(fd, input + input_len, MAX_INPUT - input_len)) > 0)
+		input_len += n;
+	close(fd);
+
+	sk = socket(AF_NETLINK, SOCK_RAW, NETLINK_ROUTE);
+	if (sk < 0) {
+		perror("socket");
+		return EXIT_FAILURE;
+	}
+
+	while (input_len - pos >= 3) {
+		flags = input[pos];
+		len = input[pos + 1] | (input[pos + 2] << 8);
+		pos += 3;
+		if (input_len - pos < len)
+			break;
+
+		if ((flags & FLAG_FIX_LEN) && len >= sizeof(nlmsg_len)) {
+			nlmsg_len = len;
+			memcpy(input + pos, &nlmsg_len, sizeof(nlmsg_len));
+		}
+		sendto(sk, input + pos, len, 0, (struct sockaddr *)&kernel_addr,
+		       sizeof(kernel_addr));
+		pos += len;
+
+		while (recv(sk, reply, sizeof(reply), MSG_DONTWAIT) > 0)
+			;
+	}
+
+	close(sk);
+	return EXIT_SUCCESS;
+}
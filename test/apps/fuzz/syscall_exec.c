@@ -0,0 +1,222 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// The executor of the syscall programs generated by `cargo osdk fuzz`.
+//
+// A program is a sequence of calls, with all the integers in little endian:
+//
+//   call := nr:u16 nr_args:u8 arg{nr_args}
+//   arg  := 0:u8 value:u64             (an immediate value)
+//         | 1:u8 len:u16 data:u8{len}  (a pointer to a copy of the data)
+//         | 2:u8 index:u8              (the result of a previous call)
+//
+// The index of a result counts back from the last call, i.e., 0 refers to the
+// result of the last call, and it wraps around the number of the calls so far.
+//
+// The program ends at the first incomplete or invalid call. The syscall
+// numbers are those of the architecture that the executor is built for.
+//
+// The program is run in a child process with a time limit, so that the
+// executor survives the signals and the blocking calls in the program. The
+// calls that would escape the child process, e.g., by creating processes or
+// sending signals to the guest agent, fail with `ENOSYS` instead.
+//
+// See `osdk/src/commands/fuzz/input.rs` for the generator.
+
+#define _GNU_SOURCE
+
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#define MAX_INPUT (64 * 1024)
+#define MAX_CALLS 64
+#define MAX_ARGS 6
+#define TIME_LIMIT_MS 3000
+
+#define ARG_IMM 0
+#define ARG_BUF 1
+#define ARG_RES 2
+
+static unsigned char input[MAX_INPUT];
+static size_t input_len, pos;
+
+static const long denied_calls[] = {
+	SYS_clone,
+#ifdef SYS_clone3
+	SYS_clone3,
+#endif
+#ifdef SYS_fork
+	SYS_fork,
+#endif
+#ifdef SYS_vfork
+	SYS_vfork,
+#endif
+	SYS_execve,
+	SYS_execveat,
+	SYS_kill,
+	SYS_tkill,
+	SYS_tgkill,
+	SYS_rt_sigqueueinfo,
+	SYS_rt_tgsigqueueinfo,
+#ifdef SYS_pidfd_send_signal
+	SYS_pidfd_send_signal,
+#endif
+	SYS_ptrace,
+	SYS_setpgid,
+	SYS_setsid,
+	SYS_mount,
+	SYS_umount2,
+	SYS_pivot_root,
+	SYS_reboot,
+};
+
+static int is_denied(long nr)
+{
+	size_t i;
+
+	for (i = 0; i < sizeof(denied_calls) / sizeof(denied_calls[0]); i++)
+		if (denied_calls[i] == nr)
+			return 1;
+	return 0;
+}
+
+// Reads a little-endian integer of `len` bytes from the input.
+static int read_le(uint64_t *value, size_t len)
+{
+	size_t i;
+
+	if (input_len - pos < len)
+		return -1;
+
+	*value = 0;
+	for (i = 0; i < len; i++)
+		*value |= (uint64_t)input[pos + i] << (8 * i);
+	pos += len;
+
+	return 0;
+}
+
+static int read_arg(long *arg, long *results, int nr_calls)
+{
+	uint64_t kind, value;
+	char *buf;
+
+	if (read_le(&kind, 1) < 0)
+		return -1;
+
+	switch (kind) {
+	case ARG_IMM:
+		if (read_le(&value, 8) < 0)
+			return -1;
+		*arg = value;
+		return 0;
+	case ARG_BUF:
+		if (read_le(&value, 2) < 0 || input_len - pos < value)
+			return -1;
+		// The data are copied, since the calls may write to the buffers.
+		buf = malloc(value + 1);
+		if (buf == NULL)
+			return -1;
+		memcpy(buf, input + pos, value);
+		pos += value;
+		*arg = (long)buf;
+		return 0;
+	case ARG_RES:
+		if (read_le(&value, 1) < 0)
+			return -1;
+		*arg = nr_calls > 0 ? results[nr_calls - 1 - value % nr_calls] :
+				      -1;
+		return 0;
+	default:
+		return -1;
+	}
+}
+
+static void run_program(void)
+{
+	long results[MAX_CALLS];
+	long args[MAX_ARGS];
+	uint64_t nr, nr_args;
+	int nr_calls, i;
+
+	for (nr_calls = 0; nr_calls < MAX_CALLS; nr_calls++) {
+		if (read_le(&nr, 2) < 0 || read_le(&nr_args, 1) < 0 ||
+		    nr_args > MAX_ARGS)
+			return;
+
+		memset(args, 0, sizeof(args));
+		for (i = 0; i < (int)nr_args; i++)
+			if (read_arg(&args[i], results, nr_calls) < 0)
+				return;
+
+		if (is_denied(nr))
+			results[nr_calls] = -ENOSYS;
+		else
+			results[nr_calls] = syscall(nr, args[0], args[1],
+						    args[2], args[3], args[4],
+						    args[5]);
+	}
+}
+
+static long elapsed_ms(const struct timespec *start)
+{
+	struct timespec now;
+
+	clock_gettime(CLOCK_MONOTONIC, &now);
+	return (now.tv_sec - start->tv_sec) * 1000 +
+	       (now.tv_nsec - start->tv_nsec) / 1000000;
+}
+
+int main(int argc, char *argv[])
+{
+	struct timespec start;
+	ssize_t n;
+	pid_t pid;
+	int fd;
+
+	if (argc != 2) {
+		fprintf(stderr, "usage: %s <PROGRAM>\n", argv[0]);
+		return EXIT_FAILURE;
+	}
+
+	fd = open(argv[1], O_RDONLY);
+	if (fd < 0) {
+		perror("open");
+		return EXIT_FAILURE;
+	}
+	while ((n = read(fd, input + input_len, MAX_INPUT - input_len)) > 0)
+		input_len += n;
+	close(fd);
+
+	pid = fork();
+	if (pid < 0) {
+		perror("fork");
+		return EXIT_FAILURE;
+	}
+	if (pid == 0) {
+		run_program();
+		_exit(EXIT_SUCCESS);
+	}
+
+	// The program may block or change its signal handlers, so the time
+	// limit is enforced by the executor with `SIGKILL`.
+	clock_gettime(CLOCK_MONOTONIC, &start);
+	while (waitpid(pid, NULL, WNOHANG) == 0) {
+		if (elapsed_ms(&start) >= TIME_LIMIT_MS) {
+			kill(pid, SIGKILL);
+			waitpid(pid, NULL, 0);
+			break;
+		}
+		usleep(10 * 1000);
+	}
+
+	return EXIT_SUCCESS;
+}
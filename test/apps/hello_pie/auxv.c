@@ -0,0 +1,70 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <elf.h>
+#include <link.h>
+#include <sys/auxv.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+extern char _start[];
+
+static __thread int tls_data = 42;
+static __thread int tls_bss;
+
+FN_TEST(entry)
+{
+	TEST_RES(getauxval(AT_ENTRY), _ret == (unsigned long)_start);
+}
+END_TEST()
+
+FN_TEST(phdr)
+{
+	const ElfW(Phdr) *phdr = (const ElfW(Phdr) *)getauxval(AT_PHDR);
+	unsigned long phnum = getauxval(AT_PHNUM);
+	unsigned long i;
+	int has_dynamic = 0;
+
+	TEST_RES(getauxval(AT_PHENT), _ret == sizeof(ElfW(Phdr)));
+	TEST_RES(phnum, _ret > 0);
+
+	for (i = 0; i < phnum; ++i)
+		if (phdr[i].p_type == PT_DYNAMIC)
+			has_dynamic = 1;
+	TEST_RES(has_dynamic, _ret == 1);
+}
+END_TEST()
+
+FN_TEST(interp_base)
+{
+	// The executable is dynamically linked, so the dynamic linker must be loaded.
+	TEST_RES(getauxval(AT_BASE), _ret != 0);
+}
+END_TEST()
+
+FN_TEST(credentials)
+{
+	TEST_RES(getauxval(AT_UID), _ret == getuid());
+	TEST_RES(getauxval(AT_EUID), _ret == geteuid());
+	TEST_RES(getauxval(AT_GID), _ret == getgid());
+	TEST_RES(getauxval(AT_EGID), _ret == getegid());
+	TEST_RES(getauxval(AT_SECURE), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(misc)
+{
+	TEST_RES(getauxval(AT_PAGESZ), _ret == getpagesize());
+	TEST_RES(getauxval(AT_CLKTCK), _ret == sysconf(_SC_CLK_TCK));
+	TEST_RES(getauxval(AT_RANDOM), _ret != 0);
+}
+END_TEST()
+
+FN_TEST(tls)
+{
+	TEST_RES(tls_data, _ret == 42);
+	TEST_RES(tls_bss, _ret == 0);
+}
+END_TEST()
@@ -0,0 +1,182 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define PAGE_SIZE 4096
+#define NR_PAGES 16
+#define NR_CHILDREN 64
+
+#define FILE_NAME "/tmp/mmap_cow_file"
+
+static void fill_pages(char *addr, int nr_pages, char base)
+{
+	for (int i = 0; i < nr_pages; ++i)
+		memset(addr + i * PAGE_SIZE, base + i, PAGE_SIZE);
+}
+
+static int check_pages(const char *addr, int nr_pages, char base)
+{
+	for (int i = 0; i < nr_pages; ++i)
+		for (int j = 0; j < PAGE_SIZE; ++j)
+			if (addr[i * PAGE_SIZE + j] != (char)(base + i))
+				return 0;
+	return 1;
+}
+
+static char *map_pages(int prot, int flags, int fd)
+{
+	return (char *)CHECK_WITH((long)mmap(NULL, NR_PAGES * PAGE_SIZE, prot,
+					     flags, fd, 0),
+				  _ret != (long)MAP_FAILED);
+}
+
+static int wait_child(int pid)
+{
+	int status;
+
+	if (waitpid(pid, &status, 0) != pid)
+		return -1;
+	if (!WIFEXITED(status))
+		return -1;
+	return WEXITSTATUS(status);
+}
+
+FN_TEST(fork_storm)
+{
+	char *addr;
+	int pids[NR_CHILDREN];
+
+	addr = map_pages(PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS,
+			 -1);
+	fill_pages(addr, NR_PAGES, 'a');
+
+	for (int i = 0; i < NR_CHILDREN; ++i) {
+		pids[i] = TEST_SUCC(fork());
+		if (pids[i] == 0) {
+			// Write to a different page in each child.
+			addr[(i % NR_PAGES) * PAGE_SIZE] = 'X';
+			if (addr[(i % NR_PAGES) * PAGE_SIZE] != 'X')
+				_exit(1);
+			addr[(i % NR_PAGES) * PAGE_SIZE] = 'a' + i % NR_PAGES;
+			_exit(check_pages(addr, NR_PAGES, 'a') ? 0 : 1);
+		}
+	}
+
+	// The parent writes while the children are running.
+	addr[PAGE_SIZE + 1] = 'Y';
+	addr[PAGE_SIZE + 1] = 'b';
+
+	for (int i = 0; i < NR_CHILDREN; ++i)
+		TEST_RES(wait_child(pids[i]), _ret == 0);
+
+	TEST_RES(check_pages(addr, NR_PAGES, 'a'), _ret == 1);
+	TEST_SUCC(munmap(addr, NR_PAGES * PAGE_SIZE));
+}
+END_TEST()
+
+FN_TEST(cow_after_mprotect)
+{
+	char *addr;
+	int pid;
+
+	addr = map_pages(PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS,
+			 -1);
+	fill_pages(addr, NR_PAGES, 'a');
+	TEST_SUCC(mprotect(addr, NR_PAGES * PAGE_SIZE, PROT_READ));
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		// Making the pages writable again must not make them shared with the parent.
+		if (mprotect(addr, NR_PAGES * PAGE_SIZE,
+			     PROT_READ | PROT_WRITE) < 0)
+			_exit(1);
+		fill_pages(addr, NR_PAGES, 'A');
+		_exit(check_pages(addr, NR_PAGES, 'A') ? 0 : 1);
+	}
+	TEST_RES(wait_child(pid), _ret == 0);
+	TEST_RES(check_pages(addr, NR_PAGES, 'a'), _ret == 1);
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		usleep(100 * 1000);
+		_exit(check_pages(addr, NR_PAGES, 'a') ? 0 : 1);
+	}
+	// The same applies to the parent.
+	TEST_SUCC(mprotect(addr, NR_PAGES * PAGE_SIZE, PROT_READ | PROT_WRITE));
+	fill_pages(addr, NR_PAGES, 'A');
+	TEST_RES(wait_child(pid), _ret == 0);
+	TEST_RES(check_pages(addr, NR_PAGES, 'A'), _ret == 1);
+
+	TEST_SUCC(munmap(addr, NR_PAGES * PAGE_SIZE));
+}
+END_TEST()
+
+FN_TEST(private_file_mapping)
+{
+	char buf[NR_PAGES * PAGE_SIZE];
+	char *addr;
+	int fd, pid;
+
+	fd = TEST_SUCC(open(FILE_NAME, O_RDWR | O_CREAT | O_TRUNC, 0644));
+	fill_pages(buf, NR_PAGES, 'a');
+	TEST_RES(write(fd, buf, sizeof(buf)), _ret == sizeof(buf));
+
+	addr = map_pages(PROT_READ, MAP_PRIVATE, fd);
+	// Map the pages in the page cache.
+	TEST_RES(check_pages(addr, NR_PAGES, 'a'), _ret == 1);
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		if (mprotect(addr, sizeof(buf), PROT_READ | PROT_WRITE) < 0)
+			_exit(1);
+		fill_pages(addr, NR_PAGES, 'A');
+		_exit(check_pages(addr, NR_PAGES, 'A') ? 0 : 1);
+	}
+	TEST_RES(wait_child(pid), _ret == 0);
+
+	// Neither the file nor the mapping of the parent is changed.
+	TEST_RES(check_pages(addr, NR_PAGES, 'a'), _ret == 1);
+	TEST_RES(pread(fd, buf, sizeof(buf), 0),
+		 _ret == sizeof(buf) && check_pages(buf, NR_PAGES, 'a'));
+
+	TEST_SUCC(mprotect(addr, sizeof(buf), PROT_READ | PROT_WRITE));
+	fill_pages(addr, NR_PAGES, 'A');
+	TEST_RES(pread(fd, buf, sizeof(buf), 0),
+		 _ret == sizeof(buf) && check_pages(buf, NR_PAGES, 'a'));
+
+	TEST_SUCC(munmap(addr, sizeof(buf)));
+	TEST_SUCC(close(fd));
+	TEST_SUCC(unlink(FILE_NAME));
+}
+END_TEST()
+
+FN_TEST(shared_mapping)
+{
+	char *addr;
+	int pid;
+
+	addr = map_pages(PROT_READ | PROT_WRITE, MAP_SHARED | MAP_ANONYMOUS,
+			 -1);
+	fill_pages(addr, NR_PAGES, 'a');
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		fill_pages(addr, NR_PAGES, 'A');
+		_exit(0);
+	}
+	TEST_RES(wait_child(pid), _ret == 0);
+
+	// The writes of the child are visible to the parent.
+	TEST_RES(check_pages(addr, NR_PAGES, 'A'), _ret == 1);
+
+	TEST_SUCC(munmap(addr, NR_PAGES * PAGE_SIZE));
+}
+END_TEST()
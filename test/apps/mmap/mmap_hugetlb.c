@@ -0,0 +1,214 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <sys/ipc.h>
+#include <sys/mman.h>
+#include <sys/mount.h>
+#include <sys/shm.h>
+#include <sys/stat.h>
+#include <sys/vfs.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define MOUNT_DIR "/tmp/hugetlbfs"
+#define HUGETLBFS_MAGIC 0x958458f6
+
+#define MB (1024UL * 1024UL)
+#define HPAGE_SIZE (2 * MB)
+
+static long read_vm(const char *name)
+{
+	char path[64], buf[32] = {};
+	int fd, ret;
+
+	snprintf(path, sizeof(path), "/proc/sys/vm/%s", name);
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+	ret = read(fd, buf, sizeof(buf) - 1);
+	close(fd);
+
+	return ret < 0 ? -1 : atol(buf);
+}
+
+static int write_nr_hugepages(const char *val)
+{
+	int fd, ret;
+
+	fd = open("/proc/sys/vm/nr_hugepages", O_WRONLY);
+	if (fd < 0)
+		return -1;
+	ret = write(fd, val, strlen(val));
+	close(fd);
+
+	return ret;
+}
+
+static long read_meminfo(const char *field)
+{
+	char buf[2048] = {}, *pos;
+	int fd, ret;
+
+	fd = open("/proc/meminfo", O_RDONLY);
+	if (fd < 0)
+		return -1;
+	ret = read(fd, buf, sizeof(buf) - 1);
+	close(fd);
+	if (ret < 0)
+		return -1;
+
+	pos = strstr(buf, field);
+	if (pos == NULL)
+		return -1;
+
+	return atol(pos + strlen(field) + 1);
+}
+
+static void *map_huge(size_t size, int flags)
+{
+	return mmap(NULL, size, PROT_READ | PROT_WRITE,
+		    flags | MAP_ANONYMOUS | MAP_HUGETLB, -1, 0);
+}
+
+FN_SETUP(pool)
+{
+	CHECK_WITH(read_meminfo("Hugepagesize:"), _ret == HPAGE_SIZE / 1024);
+	CHECK(write_nr_hugepages("4"));
+	CHECK(mkdir(MOUNT_DIR, 0755));
+}
+END_SETUP()
+
+FN_TEST(pool_size)
+{
+	TEST_RES(read_vm("nr_hugepages"), _ret == 4);
+	TEST_RES(read_meminfo("HugePages_Total:"), _ret == 4);
+	TEST_RES(read_meminfo("HugePages_Free:"), _ret == 4);
+	TEST_RES(read_meminfo("HugePages_Rsvd:"), _ret == 0);
+	TEST_RES(read_meminfo("Hugetlb:"), _ret == 4 * HPAGE_SIZE / 1024);
+
+	TEST_ERRNO(write_nr_hugepages("abc"), EINVAL);
+	TEST_RES(read_vm("nr_hugepages"), _ret == 4);
+}
+END_TEST()
+
+FN_TEST(map_hugetlb)
+{
+	char *addr;
+
+	// The length is rounded up to the huge page size.
+	addr = (char *)TEST_RES((long)map_huge(3 * MB, MAP_SHARED),
+				_ret != (long)MAP_FAILED);
+	TEST_RES(read_meminfo("HugePages_Free:"), _ret == 2);
+	memset(addr, 'a', 2 * HPAGE_SIZE);
+	TEST_RES(addr[2 * HPAGE_SIZE - 1], _ret == 'a');
+	TEST_SUCC(munmap(addr, 2 * HPAGE_SIZE));
+	TEST_RES(read_meminfo("HugePages_Free:"), _ret == 4);
+
+	addr = (char *)TEST_RES((long)map_huge(HPAGE_SIZE, MAP_PRIVATE),
+				_ret != (long)MAP_FAILED);
+	TEST_RES(read_meminfo("HugePages_Free:"), _ret == 3);
+	addr[0] = 'a';
+	TEST_SUCC(munmap(addr, HPAGE_SIZE));
+	TEST_RES(read_meminfo("HugePages_Free:"), _ret == 4);
+
+	// The pool is used up.
+	TEST_ERRNO((long)map_huge(5 * HPAGE_SIZE, MAP_SHARED), ENOMEM);
+	TEST_RES(read_meminfo("HugePages_Free:"), _ret == 4);
+
+	// The huge page size is encoded in the flags.
+	addr = (char *)TEST_RES(
+		(long)map_huge(HPAGE_SIZE, MAP_SHARED | 21 << MAP_HUGE_SHIFT),
+		_ret != (long)MAP_FAILED);
+	TEST_SUCC(munmap(addr, HPAGE_SIZE));
+	TEST_ERRNO((long)map_huge(HPAGE_SIZE,
+				  MAP_SHARED | 22 << MAP_HUGE_SHIFT),
+		   EINVAL);
+}
+END_TEST()
+
+FN_TEST(shm_hugetlb)
+{
+	int shmid;
+	char *addr;
+
+	shmid = TEST_SUCC(
+		shmget(IPC_PRIVATE, MB, IPC_CREAT | SHM_HUGETLB | 0600));
+	TEST_RES(read_meminfo("HugePages_Free:"), _ret == 3);
+
+	addr = (char *)TEST_RES((long)shmat(shmid, NULL, 0), _ret != -1);
+	addr[0] = 'a';
+	TEST_SUCC(shmdt(addr));
+
+	TEST_SUCC(shmctl(shmid, IPC_RMID, NULL));
+	TEST_RES(read_meminfo("HugePages_Free:"), _ret == 4);
+
+	TEST_ERRNO(shmget(IPC_PRIVATE, 5 * HPAGE_SIZE,
+			  IPC_CREAT | SHM_HUGETLB | 0600),
+		   ENOMEM);
+}
+END_TEST()
+
+FN_TEST(hugetlbfs)
+{
+	struct statfs stat;
+	char *addr, buf[4];
+	int fd;
+
+	TEST_ERRNO(mount("none", MOUNT_DIR, "hugetlbfs", 0, "pagesize=4M"),
+		   EINVAL);
+	TEST_ERRNO(mount("none", MOUNT_DIR, "hugetlbfs", 0, "min_size=10M"),
+		   ENOMEM);
+
+	TEST_SUCC(mount("none", MOUNT_DIR, "hugetlbfs", 0,
+			"pagesize=2M,size=4M,min_size=2M"));
+	TEST_RES(statfs(MOUNT_DIR, &stat),
+		 stat.f_type == HUGETLBFS_MAGIC && stat.f_bsize == HPAGE_SIZE);
+	TEST_RES(read_meminfo("HugePages_Rsvd:"), _ret == 1);
+
+	fd = TEST_SUCC(open(MOUNT_DIR "/file", O_RDWR | O_CREAT, 0600));
+	TEST_ERRNO(write(fd, "a", 1), EINVAL);
+	TEST_ERRNO(ftruncate(fd, MB), EINVAL);
+
+	// The reserved page is used first.
+	TEST_SUCC(ftruncate(fd, HPAGE_SIZE));
+	TEST_RES(read_meminfo("HugePages_Rsvd:"), _ret == 0);
+	TEST_RES(read_meminfo("HugePages_Free:"), _ret == 3);
+
+	// The mapping extends the file, but not beyond the size of the mount.
+	TEST_ERRNO((long)mmap(NULL, 3 * HPAGE_SIZE, PROT_READ | PROT_WRITE,
+			      MAP_SHARED, fd, 0),
+		   ENOMEM);
+	addr = (char *)TEST_RES((long)mmap(NULL, 2 * HPAGE_SIZE,
+					   PROT_READ | PROT_WRITE, MAP_SHARED,
+					   fd, 0),
+				_ret != (long)MAP_FAILED);
+	TEST_RES(lseek(fd, 0, SEEK_END), _ret == 2 * HPAGE_SIZE);
+	memcpy(addr + HPAGE_SIZE, "abc", 3);
+	TEST_RES(pread(fd, buf, 3, HPAGE_SIZE),
+		 _ret == 3 && memcmp(buf, "abc", 3) == 0);
+	TEST_SUCC(munmap(addr, 2 * HPAGE_SIZE));
+	TEST_RES(read_meminfo("HugePages_Free:"), _ret == 2);
+
+	// The page below the minimum size is reserved again.
+	TEST_SUCC(ftruncate(fd, 0));
+	TEST_RES(read_meminfo("HugePages_Free:"), _ret == 4);
+	TEST_RES(read_meminfo("HugePages_Rsvd:"), _ret == 1);
+
+	TEST_SUCC(close(fd));
+	TEST_SUCC(unlink(MOUNT_DIR "/file"));
+	TEST_SUCC(umount(MOUNT_DIR));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(rmdir(MOUNT_DIR));
+	CHECK(write_nr_hugepages("0"));
+}
+END_SETUP()
@@ -0,0 +1,208 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define KSM_DIR "/sys/kernel/mm/ksm/"
+
+#define PAGE_SIZE 4096
+#define NR_SAME_PAGES 8
+#define NR_ZERO_PAGES 8
+#define NR_UNIQUE_PAGES 8
+#define NR_PAGES (NR_SAME_PAGES + NR_ZERO_PAGES + NR_UNIQUE_PAGES)
+
+#define SAME_PAGE(i) (addr + (i) * PAGE_SIZE)
+#define ZERO_PAGE(i) (addr + (NR_SAME_PAGES + (i)) * PAGE_SIZE)
+#define UNIQUE_PAGE(i) \
+	(addr + (NR_SAME_PAGES + NR_ZERO_PAGES + (i)) * PAGE_SIZE)
+
+static char *addr;
+
+static int read_ksm(const char *name)
+{
+	char path[64], buf[32] = {};
+	int fd, ret;
+
+	snprintf(path, sizeof(path), KSM_DIR "%s", name);
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+	ret = read(fd, buf, sizeof(buf) - 1);
+	close(fd);
+
+	return ret < 0 ? -1 : atoi(buf);
+}
+
+static int write_ksm(const char *name, const char *val)
+{
+	char path[64];
+	int fd, ret;
+
+	snprintf(path, sizeof(path), KSM_DIR "%s", name);
+	fd = open(path, O_WRONLY);
+	if (fd < 0)
+		return -1;
+	ret = write(fd, val, strlen(val));
+	close(fd);
+
+	return ret;
+}
+
+// Waits until the KSM thread finishes the current full scan and two more full scans, after which
+// all the identical pages should have been merged.
+static int wait_full_scans(void)
+{
+	int start, i;
+
+	start = read_ksm("full_scans");
+	if (start < 0)
+		return -1;
+
+	for (i = 0; i < 1000; ++i) {
+		if (read_ksm("full_scans") >= start + 3)
+			return 0;
+		usleep(10 * 1000);
+	}
+
+	return -1;
+}
+
+static int check_pages(void)
+{
+	int i;
+
+	for (i = 0; i < NR_SAME_PAGES; ++i)
+		if (SAME_PAGE(i)[0] != 'a' || SAME_PAGE(i)[PAGE_SIZE - 1] != 'a')
+			return -1;
+	for (i = 0; i < NR_ZERO_PAGES; ++i)
+		if (ZERO_PAGE(i)[0] != 0 || ZERO_PAGE(i)[PAGE_SIZE - 1] != 0)
+			return -1;
+	for (i = 0; i < NR_UNIQUE_PAGES; ++i)
+		if (UNIQUE_PAGE(i)[0] != 'A' + i)
+			return -1;
+
+	return 0;
+}
+
+FN_SETUP(mmap_pages)
+{
+	int i;
+
+	addr = (char *)CHECK_WITH((long)mmap(NULL, PAGE_SIZE * NR_PAGES,
+					     PROT_READ | PROT_WRITE,
+					     MAP_PRIVATE | MAP_ANONYMOUS, -1, 0),
+				  _ret != (long)MAP_FAILED);
+
+	for (i = 0; i < NR_SAME_PAGES; ++i)
+		memset(SAME_PAGE(i), 'a', PAGE_SIZE);
+	// Touch the zero pages so that they are populated.
+	for (i = 0; i < NR_ZERO_PAGES; ++i)
+		ZERO_PAGE(i)[0] = 0;
+	for (i = 0; i < NR_UNIQUE_PAGES; ++i)
+		memset(UNIQUE_PAGE(i), 'A' + i, PAGE_SIZE);
+
+	CHECK(write_ksm("pages_to_scan", "1000"));
+	CHECK(write_ksm("sleep_millisecs", "10"));
+}
+END_SETUP()
+
+FN_TEST(ksm_stopped)
+{
+	TEST_RES(read_ksm("run"), _ret == 0);
+	TEST_RES(read_ksm("pages_to_scan"), _ret == 1000);
+	TEST_RES(read_ksm("sleep_millisecs"), _ret == 10);
+
+	TEST_ERRNO(write_ksm("pages_shared", "0"), EACCES);
+}
+END_TEST()
+
+FN_TEST(madvise_mergeable)
+{
+	char *unmapped;
+
+	unmapped = (char *)TEST_RES((long)mmap(NULL, PAGE_SIZE, PROT_READ,
+					       MAP_PRIVATE | MAP_ANONYMOUS, -1,
+					       0),
+				    _ret != (long)MAP_FAILED);
+	TEST_SUCC(munmap(unmapped, PAGE_SIZE));
+	TEST_ERRNO(madvise(unmapped, PAGE_SIZE, MADV_MERGEABLE), ENOMEM);
+
+	TEST_SUCC(madvise(addr, PAGE_SIZE * NR_PAGES, MADV_MERGEABLE));
+}
+END_TEST()
+
+FN_TEST(merge_pages)
+{
+	TEST_SUCC(write_ksm("run", "1"));
+	TEST_RES(read_ksm("run"), _ret == 1);
+	TEST_SUCC(wait_full_scans());
+
+	TEST_RES(read_ksm("pages_shared"), _ret == 1);
+	TEST_RES(read_ksm("pages_sharing"), _ret == NR_SAME_PAGES - 1);
+	TEST_RES(read_ksm("ksm_zero_pages"), _ret == NR_ZERO_PAGES);
+
+	TEST_SUCC(check_pages());
+}
+END_TEST()
+
+FN_TEST(write_merged_pages)
+{
+	int pid, status;
+
+	TEST_SUCC(write_ksm("run", "0"));
+
+	// Writing to a merged page breaks the sharing.
+	SAME_PAGE(0)[0] = 'b';
+	ZERO_PAGE(0)[0] = 'b';
+	TEST_RES(SAME_PAGE(0)[0], _ret == 'b');
+	TEST_RES(ZERO_PAGE(0)[0], _ret == 'b');
+	SAME_PAGE(0)[0] = 'a';
+	ZERO_PAGE(0)[0] = 0;
+	TEST_SUCC(check_pages());
+
+	// The forked child sees the same contents.
+	pid = TEST_SUCC(fork());
+	if (pid == 0)
+		exit(check_pages() ? EXIT_FAILURE : EXIT_SUCCESS);
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+}
+END_TEST()
+
+FN_TEST(unmerge_pages)
+{
+	TEST_SUCC(write_ksm("run", "1"));
+	TEST_SUCC(wait_full_scans());
+	TEST_RES(read_ksm("pages_sharing"), _ret == NR_SAME_PAGES - 1);
+
+	TEST_SUCC(madvise(SAME_PAGE(0), PAGE_SIZE * 2, MADV_UNMERGEABLE));
+	TEST_SUCC(wait_full_scans());
+	TEST_RES(read_ksm("pages_shared"), _ret == 1);
+	TEST_RES(read_ksm("pages_sharing"), _ret == NR_SAME_PAGES - 3);
+	TEST_SUCC(check_pages());
+
+	TEST_SUCC(write_ksm("run", "2"));
+	TEST_RES(read_ksm("run"), _ret == 2);
+	TEST_RES(read_ksm("pages_shared"), _ret == 0);
+	TEST_RES(read_ksm("pages_sharing"), _ret == 0);
+	TEST_RES(read_ksm("ksm_zero_pages"), _ret == 0);
+	TEST_SUCC(check_pages());
+
+	TEST_SUCC(write_ksm("run", "0"));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(munmap(addr, PAGE_SIZE * NR_PAGES));
+}
+END_SETUP()
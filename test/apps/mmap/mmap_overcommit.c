@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <sys/mman.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define VM_DIR "/proc/sys/vm/"
+
+#define MB (1024UL * 1024UL)
+#define HUGE_SIZE (1UL << 45)
+
+static long read_vm(const char *name)
+{
+	char path[64], buf[32] = {};
+	int fd, ret;
+
+	snprintf(path, sizeof(path), VM_DIR "%s", name);
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+	ret = read(fd, buf, sizeof(buf) - 1);
+	close(fd);
+
+	return ret < 0 ? -1 : atol(buf);
+}
+
+static int write_vm(const char *name, const char *val)
+{
+	char path[64];
+	int fd, ret;
+
+	snprintf(path, sizeof(path), VM_DIR "%s", name);
+	fd = open(path, O_WRONLY);
+	if (fd < 0)
+		return -1;
+	ret = write(fd, val, strlen(val));
+	close(fd);
+
+	return ret;
+}
+
+static long read_meminfo(const char *field)
+{
+	char buf[1024] = {}, *pos;
+	int fd, ret;
+
+	fd = open("/proc/meminfo", O_RDONLY);
+	if (fd < 0)
+		return -1;
+	ret = read(fd, buf, sizeof(buf) - 1);
+	close(fd);
+	if (ret < 0)
+		return -1;
+
+	pos = strstr(buf, field);
+	if (pos == NULL)
+		return -1;
+
+	return atol(pos + strlen(field) + 1);
+}
+
+static void *map(size_t size, int prot, int flags)
+{
+	return mmap(NULL, size, prot, flags | MAP_ANONYMOUS, -1, 0);
+}
+
+// Limits the memory that can be newly committed to about `extra` bytes.
+static int set_commit_limit(size_t extra)
+{
+	char buf[32];
+	long committed;
+
+	committed = read_meminfo("Committed_AS:");
+	if (committed < 0)
+		return -1;
+
+	snprintf(buf, sizeof(buf), "%lu", committed + extra / 1024);
+	return write_vm("overcommit_kbytes", buf);
+}
+
+FN_TEST(default_values)
+{
+	TEST_RES(read_vm("overcommit_memory"), _ret == 0);
+	TEST_RES(read_vm("overcommit_ratio"), _ret == 50);
+	TEST_RES(read_vm("overcommit_kbytes"), _ret == 0);
+
+	TEST_RES(read_meminfo("CommitLimit:"),
+		 _ret > 0 && _ret <= read_meminfo("MemTotal:") / 2);
+	TEST_RES(read_meminfo("Committed_AS:"), _ret > 0);
+}
+END_TEST()
+
+FN_TEST(write_values)
+{
+	TEST_ERRNO(write_vm("overcommit_memory", "3"), EINVAL);
+	TEST_ERRNO(write_vm("overcommit_ratio", "abc"), EINVAL);
+
+	// Setting `overcommit_kbytes` clears `overcommit_ratio`, and vice versa.
+	TEST_SUCC(write_vm("overcommit_kbytes", "1024"));
+	TEST_RES(read_vm("overcommit_ratio"), _ret == 0);
+	TEST_RES(read_meminfo("CommitLimit:"), _ret == 1024);
+	TEST_SUCC(write_vm("overcommit_ratio", "50"));
+	TEST_RES(read_vm("overcommit_kbytes"), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(committed_as)
+{
+	long before;
+	char *addr;
+
+	before = TEST_SUCC(read_meminfo("Committed_AS:"));
+
+	addr = (char *)TEST_RES((long)map(64 * MB, PROT_READ | PROT_WRITE,
+					  MAP_PRIVATE),
+				_ret != (long)MAP_FAILED);
+	TEST_RES(read_meminfo("Committed_AS:"), _ret == before + 64 * 1024);
+
+	TEST_SUCC(munmap(addr, 32 * MB));
+	TEST_RES(read_meminfo("Committed_AS:"), _ret == before + 32 * 1024);
+
+	TEST_SUCC(munmap(addr + 32 * MB, 32 * MB));
+	TEST_RES(read_meminfo("Committed_AS:"), _ret == before);
+}
+END_TEST()
+
+FN_TEST(overcommit_never)
+{
+	char *addr;
+
+	TEST_SUCC(set_commit_limit(16 * MB));
+	TEST_SUCC(write_vm("overcommit_memory", "2"));
+
+	// Private writable mappings are accounted.
+	TEST_ERRNO((long)map(64 * MB, PROT_READ | PROT_WRITE, MAP_PRIVATE),
+		   ENOMEM);
+	TEST_ERRNO((long)map(64 * MB, PROT_READ | PROT_WRITE,
+			     MAP_PRIVATE | MAP_NORESERVE),
+		   ENOMEM);
+
+	// Read-only mappings are not accounted until they become writable.
+	addr = (char *)TEST_RES((long)map(64 * MB, PROT_READ, MAP_PRIVATE),
+				_ret != (long)MAP_FAILED);
+	TEST_ERRNO(mprotect(addr, 64 * MB, PROT_READ | PROT_WRITE), ENOMEM);
+	TEST_SUCC(mprotect(addr, 8 * MB, PROT_READ | PROT_WRITE));
+	addr[0] = 'a';
+	TEST_SUCC(munmap(addr, 64 * MB));
+
+	// Small mappings are still allowed.
+	addr = (char *)TEST_RES((long)map(8 * MB, PROT_READ | PROT_WRITE,
+					  MAP_PRIVATE),
+				_ret != (long)MAP_FAILED);
+	addr[0] = 'a';
+	TEST_SUCC(munmap(addr, 8 * MB));
+
+	TEST_SUCC(write_vm("overcommit_memory", "0"));
+	TEST_SUCC(write_vm("overcommit_ratio", "50"));
+}
+END_TEST()
+
+FN_TEST(overcommit_guess)
+{
+	char *addr;
+
+	TEST_ERRNO((long)map(HUGE_SIZE, PROT_READ | PROT_WRITE, MAP_PRIVATE),
+		   ENOMEM);
+
+	addr = (char *)TEST_RES((long)map(HUGE_SIZE, PROT_READ | PROT_WRITE,
+					  MAP_PRIVATE | MAP_NORESERVE),
+				_ret != (long)MAP_FAILED);
+	addr[0] = 'a';
+	TEST_SUCC(munmap(addr, HUGE_SIZE));
+}
+END_TEST()
+
+FN_TEST(overcommit_always)
+{
+	char *addr;
+
+	TEST_SUCC(write_vm("overcommit_memory", "1"));
+
+	addr = (char *)TEST_RES((long)map(HUGE_SIZE, PROT_READ | PROT_WRITE,
+					  MAP_PRIVATE),
+				_ret != (long)MAP_FAILED);
+	addr[0] = 'a';
+	TEST_SUCC(munmap(addr, HUGE_SIZE));
+
+	TEST_SUCC(write_vm("overcommit_memory", "0"));
+}
+END_TEST()
@@ -0,0 +1,133 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define PAGE_SIZE 4096
+#define FILE_PATH "/tmp/mmap_shared_msync"
+#define FILE_SIZE (4 * PAGE_SIZE)
+
+static int fd;
+static char *map;
+
+static int file_contains(off_t offset, const char *expected)
+{
+	char buf[64];
+	size_t len = strlen(expected);
+
+	if (pread(fd, buf, len, offset) != len)
+		return 0;
+
+	return memcmp(buf, expected, len) == 0;
+}
+
+static int wait_for_child(pid_t pid)
+{
+	int status;
+
+	if (waitpid(pid, &status, 0) != pid)
+		return -1;
+
+	return WIFEXITED(status) ? WEXITSTATUS(status) : -1;
+}
+
+FN_SETUP(open_and_map)
+{
+	fd = CHECK(open(FILE_PATH, O_RDWR | O_CREAT | O_TRUNC, 0644));
+	CHECK(ftruncate(fd, FILE_SIZE));
+
+	map = (char *)CHECK_WITH((long)mmap(NULL, FILE_SIZE,
+					    PROT_READ | PROT_WRITE, MAP_SHARED,
+					    fd, 0),
+				 _ret != (long)MAP_FAILED);
+}
+END_SETUP()
+
+FN_TEST(msync_invalid)
+{
+	char *anon;
+
+	TEST_ERRNO(msync(map + 1, PAGE_SIZE, MS_SYNC), EINVAL);
+	TEST_ERRNO(msync(map, PAGE_SIZE, MS_SYNC | MS_ASYNC), EINVAL);
+	TEST_ERRNO(msync(map, PAGE_SIZE, 0x100), EINVAL);
+	TEST_SUCC(msync(map, 0, MS_SYNC));
+
+	// The range contains unmapped pages.
+	anon = (char *)TEST_SUCC((long)mmap(NULL, 2 * PAGE_SIZE, PROT_READ,
+					    MAP_PRIVATE | MAP_ANONYMOUS, -1,
+					    0));
+	TEST_SUCC(munmap(anon + PAGE_SIZE, PAGE_SIZE));
+	TEST_ERRNO(msync(anon, 2 * PAGE_SIZE, MS_SYNC), ENOMEM);
+	TEST_SUCC(msync(anon, PAGE_SIZE, MS_SYNC));
+	TEST_SUCC(munmap(anon, PAGE_SIZE));
+}
+END_TEST()
+
+FN_TEST(msync_write_back)
+{
+	strcpy(map, "first");
+	TEST_SUCC(msync(map, FILE_SIZE, MS_SYNC));
+	TEST_RES(file_contains(0, "first"), _ret);
+
+	// The page is written again after it has been written back.
+	strcpy(map, "second");
+	TEST_SUCC(msync(map, PAGE_SIZE, MS_ASYNC));
+	TEST_SUCC(msync(map, PAGE_SIZE, MS_SYNC | MS_INVALIDATE));
+	TEST_RES(file_contains(0, "second"), _ret);
+
+	// The writes to the file are visible through the mapping.
+	TEST_RES(pwrite(fd, "third", 6, PAGE_SIZE), _ret == 6);
+	TEST_RES(strcmp(map + PAGE_SIZE, "third"), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(shared_between_processes)
+{
+	char *other_map;
+	pid_t pid;
+
+	other_map = (char *)TEST_SUCC(
+		(long)mmap(NULL, FILE_SIZE, PROT_READ, MAP_SHARED, fd, 0));
+	TEST_RES(other_map[2 * PAGE_SIZE], _ret == 0);
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		strcpy(map + 2 * PAGE_SIZE, "child");
+		_exit(msync(map, FILE_SIZE, MS_SYNC) == 0 ? 0 : 1);
+	}
+	TEST_RES(wait_for_child(pid), _ret == 0);
+
+	// The stores of the child are visible to all mappings of the file.
+	TEST_RES(strcmp(map + 2 * PAGE_SIZE, "child"), _ret == 0);
+	TEST_RES(strcmp(other_map + 2 * PAGE_SIZE, "child"), _ret == 0);
+	TEST_RES(file_contains(2 * PAGE_SIZE, "child"), _ret);
+
+	TEST_SUCC(munmap(other_map, FILE_SIZE));
+
+	// `MAP_SHARED_VALIDATE` also creates a shared mapping.
+	other_map = (char *)TEST_SUCC((long)mmap(NULL, FILE_SIZE,
+						 PROT_READ | PROT_WRITE,
+						 MAP_SHARED_VALIDATE, fd, 0));
+	strcpy(other_map + 2 * PAGE_SIZE, "validate");
+	TEST_RES(strcmp(map + 2 * PAGE_SIZE, "validate"), _ret == 0);
+	TEST_SUCC(munmap(other_map, FILE_SIZE));
+}
+END_TEST()
+
+FN_TEST(write_back_on_unmap)
+{
+	strcpy(map + 3 * PAGE_SIZE, "unmapped");
+	TEST_SUCC(munmap(map, FILE_SIZE));
+	TEST_RES(file_contains(3 * PAGE_SIZE, "unmapped"), _ret);
+
+	TEST_SUCC(close(fd));
+	TEST_SUCC(unlink(FILE_PATH));
+}
+END_TEST()
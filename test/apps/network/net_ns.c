@@ -0,0 +1,216 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <arpa/inet.h>
+#include <linux/rtnetlink.h>
+#include <net/if.h>
+#include <netinet/in.h>
+#include <sched.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "test.h"
+
+#define TEST_PORT 8765
+#define USER_ID 1000
+
+static int sk_listen;
+static int sk_old_ns;
+static struct sockaddr_in lo_addr;
+
+FN_SETUP(sockets)
+{
+	lo_addr.sin_family = AF_INET;
+	lo_addr.sin_port = htons(TEST_PORT);
+	lo_addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+
+	sk_listen = CHECK(socket(AF_INET, SOCK_STREAM, 0));
+	CHECK(bind(sk_listen, (struct sockaddr *)&lo_addr, sizeof(lo_addr)));
+	CHECK(listen(sk_listen, 2));
+
+	// This socket is created in the initial network namespace.
+	sk_old_ns = CHECK(socket(AF_INET, SOCK_STREAM, 0));
+}
+END_SETUP()
+
+struct link_info {
+	int count;
+	int lo_index;
+};
+
+// Dumps the links in the network namespace of the netlink socket.
+static int dump_links(int sk, struct link_info *info)
+{
+	struct {
+		struct nlmsghdr hdr;
+		struct ifinfomsg ifi;
+	} req;
+	char buf[8192];
+	struct nlmsghdr *hdr;
+	int len;
+
+	memset(&req, 0, sizeof(req));
+	req.hdr.nlmsg_len = sizeof(req);
+	req.hdr.nlmsg_type = RTM_GETLINK;
+	req.hdr.nlmsg_flags = NLM_F_REQUEST | NLM_F_DUMP;
+	req.ifi.ifi_family = AF_UNSPEC;
+
+	if (send(sk, &req, sizeof(req), 0) != sizeof(req))
+		return -1;
+
+	info->count = 0;
+	info->lo_index = 0;
+	for (;;) {
+		len = recv(sk, buf, sizeof(buf), 0);
+		if (len < 0)
+			return -1;
+
+		for (hdr = (struct nlmsghdr *)buf; NLMSG_OK(hdr, len);
+		     hdr = NLMSG_NEXT(hdr, len)) {
+			struct ifinfomsg *ifi = NLMSG_DATA(hdr);
+
+			if (hdr->nlmsg_type == NLMSG_DONE)
+				return 0;
+			if (hdr->nlmsg_type != RTM_NEWLINK)
+				return -1;
+			info->count++;
+			if (ifi->ifi_flags & IFF_LOOPBACK)
+				info->lo_index = ifi->ifi_index;
+		}
+	}
+}
+
+// Sets the address of the loopback interface to the same value, and returns the error code in
+// the acknowledgment.
+static int replace_lo_addr(int sk, int lo_index)
+{
+	struct {
+		struct nlmsghdr hdr;
+		struct ifaddrmsg ifa;
+		struct rtattr local_attr;
+		struct in_addr local;
+	} req;
+	char buf[4096];
+	struct nlmsghdr *hdr = (struct nlmsghdr *)buf;
+
+	memset(&req, 0, sizeof(req));
+	req.hdr.nlmsg_len = sizeof(req);
+	req.hdr.nlmsg_type = RTM_NEWADDR;
+	req.hdr.nlmsg_flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_REPLACE;
+	req.ifa.ifa_family = AF_INET;
+	req.ifa.ifa_prefixlen = 8;
+	req.ifa.ifa_index = lo_index;
+	req.local_attr.rta_len = RTA_LENGTH(sizeof(req.local));
+	req.local_attr.rta_type = IFA_LOCAL;
+	req.local.s_addr = htonl(INADDR_LOOPBACK);
+
+	if (send(sk, &req, sizeof(req), 0) != sizeof(req))
+		return -1;
+	if (recv(sk, buf, sizeof(buf), 0) < 0 || hdr->nlmsg_type != NLMSG_ERROR)
+		return -1;
+	return ((struct nlmsgerr *)NLMSG_DATA(hdr))->error;
+}
+
+// Runs the function in a child process, which switches to a non-root user if `as_user` is set.
+static int run_in_child(void (*func)(void), int as_user)
+{
+	int status;
+	pid_t pid;
+
+	pid = fork();
+	if (pid < 0)
+		return -1;
+	if (pid == 0) {
+		if (as_user)
+			CHECK(setresuid(USER_ID, USER_ID, USER_ID));
+		func();
+		exit(EXIT_SUCCESS);
+	}
+
+	if (waitpid(pid, &status, 0) != pid)
+		return -1;
+	return WIFEXITED(status) ? WEXITSTATUS(status) : -1;
+}
+
+static void isolated_loopback(void)
+{
+	struct link_info info;
+	int sk, sk_nl;
+
+	CHECK(unshare(CLONE_NEWNET));
+
+	// The new namespace only has its own loopback interface.
+	sk_nl = CHECK(socket(AF_NETLINK, SOCK_RAW, NETLINK_ROUTE));
+	CHECK(dump_links(sk_nl, &info));
+	CHECK_WITH(info.count, _ret == 1);
+	CHECK_WITH(info.lo_index, _ret > 0);
+	CHECK(close(sk_nl));
+
+	// The listening socket in the initial namespace cannot be reached.
+	sk = CHECK(socket(AF_INET, SOCK_STREAM, 0));
+	CHECK_WITH(connect(sk, (struct sockaddr *)&lo_addr, sizeof(lo_addr)),
+		   _ret < 0 && errno == ECONNREFUSED);
+	CHECK(close(sk));
+
+	// The port is not in use in the new namespace.
+	sk = CHECK(socket(AF_INET, SOCK_STREAM, 0));
+	CHECK(bind(sk, (struct sockaddr *)&lo_addr, sizeof(lo_addr)));
+	CHECK(close(sk));
+
+	// The sockets created before stay in the initial namespace.
+	CHECK(connect(sk_old_ns, (struct sockaddr *)&lo_addr, sizeof(lo_addr)));
+}
+
+FN_TEST(isolated_loopback)
+{
+	int sk;
+
+	TEST_RES(run_in_child(isolated_loopback, 0), _ret == 0);
+
+	sk = TEST_SUCC(accept(sk_listen, NULL, NULL));
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+static void unprivileged_ns(void)
+{
+	struct link_info info;
+	int sk_nl;
+
+	CHECK_WITH(unshare(CLONE_NEWNET), _ret < 0 && errno == EPERM);
+
+	// The network namespace owned by a new user namespace can be configured.
+	CHECK(unshare(CLONE_NEWUSER | CLONE_NEWNET));
+	sk_nl = CHECK(socket(AF_NETLINK, SOCK_RAW, NETLINK_ROUTE));
+	CHECK(dump_links(sk_nl, &info));
+	CHECK_WITH(replace_lo_addr(sk_nl, info.lo_index), _ret == 0);
+	CHECK(close(sk_nl));
+}
+
+static void unprivileged_config(void)
+{
+	struct link_info info;
+	int sk_nl;
+
+	// The initial network namespace cannot be configured.
+	sk_nl = CHECK(socket(AF_NETLINK, SOCK_RAW, NETLINK_ROUTE));
+	CHECK(dump_links(sk_nl, &info));
+	CHECK(unshare(CLONE_NEWUSER));
+	CHECK_WITH(replace_lo_addr(sk_nl, info.lo_index), _ret == -EPERM);
+	CHECK(close(sk_nl));
+}
+
+FN_TEST(unprivileged)
+{
+	TEST_RES(run_in_child(unprivileged_ns, 1), _ret == 0);
+	TEST_RES(run_in_child(unprivileged_config, 1), _ret == 0);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_old_ns));
+	CHECK(close(sk_listen));
+}
+END_SETUP()
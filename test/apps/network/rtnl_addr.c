@@ -0,0 +1,286 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <arpa/inet.h>
+#include <linux/capability.h>
+#include <linux/rtnetlink.h>
+#include <net/if.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "test.h"
+
+#define LOOPBACK_NAME "lo"
+#define LOOPBACK_ADDR "127.0.0.1"
+#define LOOPBACK_PREFIX_LEN 8
+
+static int sk_req;
+static int sk_mcast;
+static int lo_index;
+static struct in_addr lo_addr;
+static unsigned int seq;
+
+struct addr_req {
+	struct nlmsghdr hdr;
+	struct ifaddrmsg ifa;
+	struct rtattr local_attr;
+	struct in_addr local;
+};
+
+struct link_req {
+	struct nlmsghdr hdr;
+	struct ifinfomsg ifi;
+};
+
+FN_SETUP(sockets)
+{
+	struct sockaddr_nl addr = {
+		.nl_family = AF_NETLINK,
+		.nl_groups = RTMGRP_IPV4_IFADDR,
+	};
+
+	sk_req = CHECK(socket(AF_NETLINK, SOCK_RAW, NETLINK_ROUTE));
+
+	sk_mcast = CHECK(
+		socket(AF_NETLINK, SOCK_RAW | SOCK_NONBLOCK, NETLINK_ROUTE));
+	CHECK(bind(sk_mcast, (struct sockaddr *)&addr, sizeof(addr)));
+
+	lo_index = CHECK_WITH(if_nametoindex(LOOPBACK_NAME), _ret > 0);
+	CHECK_WITH(inet_pton(AF_INET, LOOPBACK_ADDR, &lo_addr), _ret == 1);
+}
+END_SETUP()
+
+// Receives the acknowledgment and returns the error code in it.
+static int recv_ack(int sk, unsigned int expected_seq)
+{
+	char buf[4096];
+	struct nlmsghdr *hdr = (struct nlmsghdr *)buf;
+	struct nlmsgerr *err = NLMSG_DATA(hdr);
+
+	if (recv(sk, buf, sizeof(buf), 0) < 0)
+		return -1;
+	if (hdr->nlmsg_type != NLMSG_ERROR || hdr->nlmsg_seq != expected_seq)
+		return -1;
+	return err->error;
+}
+
+static int send_addr_req(int sk, int type, int flags, struct in_addr addr)
+{
+	struct addr_req req;
+
+	memset(&req, 0, sizeof(req));
+	req.hdr.nlmsg_len = sizeof(req);
+	req.hdr.nlmsg_type = type;
+	req.hdr.nlmsg_flags = NLM_F_REQUEST | NLM_F_ACK | flags;
+	req.hdr.nlmsg_seq = ++seq;
+	req.ifa.ifa_family = AF_INET;
+	req.ifa.ifa_prefixlen = LOOPBACK_PREFIX_LEN;
+	req.ifa.ifa_index = lo_index;
+	req.local_attr.rta_len = RTA_LENGTH(sizeof(req.local));
+	req.local_attr.rta_type = IFA_LOCAL;
+	req.local = addr;
+
+	if (send(sk, &req, sizeof(req), 0) != sizeof(req))
+		return -1;
+	return recv_ack(sk, seq);
+}
+
+// Returns whether the next multicast message is the expected notification.
+static int recv_addr_notification(int type, struct in_addr addr)
+{
+	char buf[4096];
+	struct nlmsghdr *hdr = (struct nlmsghdr *)buf;
+	struct ifaddrmsg *ifa = NLMSG_DATA(hdr);
+	struct rtattr *attr = IFA_RTA(ifa);
+	int attr_len;
+
+	if (recv(sk_mcast, buf, sizeof(buf), 0) < 0)
+		return 0;
+	if (hdr->nlmsg_type != type || ifa->ifa_family != AF_INET ||
+	    ifa->ifa_index != lo_index ||
+	    ifa->ifa_prefixlen != LOOPBACK_PREFIX_LEN)
+		return 0;
+
+	attr_len = IFA_PAYLOAD(hdr);
+	for (; RTA_OK(attr, attr_len); attr = RTA_NEXT(attr, attr_len)) {
+		if (attr->rta_type == IFA_LOCAL)
+			return memcmp(RTA_DATA(attr), &addr, sizeof(addr)) == 0;
+	}
+	return 0;
+}
+
+// Returns the number of the IPv4 addresses of the loopback interface.
+static int count_lo_addrs(void)
+{
+	struct {
+		struct nlmsghdr hdr;
+		struct ifaddrmsg ifa;
+	} req;
+	char buf[8192];
+	struct nlmsghdr *hdr;
+	int len, count = 0;
+
+	memset(&req, 0, sizeof(req));
+	req.hdr.nlmsg_len = sizeof(req);
+	req.hdr.nlmsg_type = RTM_GETADDR;
+	req.hdr.nlmsg_flags = NLM_F_REQUEST | NLM_F_DUMP;
+	req.hdr.nlmsg_seq = ++seq;
+	req.ifa.ifa_family = AF_INET;
+
+	if (send(sk_req, &req, sizeof(req), 0) != sizeof(req))
+		return -1;
+
+	for (;;) {
+		len = recv(sk_req, buf, sizeof(buf), 0);
+		if (len < 0)
+			return -1;
+
+		for (hdr = (struct nlmsghdr *)buf; NLMSG_OK(hdr, len);
+		     hdr = NLMSG_NEXT(hdr, len)) {
+			struct ifaddrmsg *ifa = NLMSG_DATA(hdr);
+
+			if (hdr->nlmsg_type == NLMSG_DONE)
+				return count;
+			if (hdr->nlmsg_type != RTM_NEWADDR)
+				return -1;
+			if (ifa->ifa_family == AF_INET &&
+			    ifa->ifa_index == lo_index)
+				count++;
+		}
+	}
+}
+
+FN_TEST(new_addr)
+{
+	struct in_addr addr = lo_addr;
+
+	TEST_RES(send_addr_req(sk_req, RTM_NEWADDR, NLM_F_REPLACE, addr),
+		 _ret == 0);
+	TEST_RES(recv_addr_notification(RTM_NEWADDR, addr), _ret == 1);
+
+	TEST_RES(send_addr_req(sk_req, RTM_NEWADDR, 0, addr), _ret == -EEXIST);
+	TEST_RES(send_addr_req(sk_req, RTM_NEWADDR, NLM_F_REPLACE | NLM_F_EXCL,
+			       addr),
+		 _ret == -EEXIST);
+	TEST_ERRNO(recv(sk_mcast, &addr, sizeof(addr), 0), EAGAIN);
+
+	TEST_RES(count_lo_addrs(), _ret == 1);
+}
+END_TEST()
+
+FN_TEST(del_addr)
+{
+	struct in_addr addr = lo_addr;
+
+	TEST_RES(send_addr_req(sk_req, RTM_DELADDR, 0, addr), _ret == 0);
+	TEST_RES(recv_addr_notification(RTM_DELADDR, addr), _ret == 1);
+	TEST_RES(count_lo_addrs(), _ret == 0);
+
+	TEST_RES(send_addr_req(sk_req, RTM_DELADDR, 0, addr),
+		 _ret == -EADDRNOTAVAIL);
+	TEST_ERRNO(recv(sk_mcast, &addr, sizeof(addr), 0), EAGAIN);
+
+	TEST_RES(send_addr_req(sk_req, RTM_NEWADDR, NLM_F_CREATE | NLM_F_EXCL,
+			       addr),
+		 _ret == 0);
+	TEST_RES(recv_addr_notification(RTM_NEWADDR, addr), _ret == 1);
+	TEST_RES(count_lo_addrs(), _ret == 1);
+}
+END_TEST()
+
+static int drop_effective_caps(void)
+{
+	struct __user_cap_header_struct header = {
+		.version = _LINUX_CAPABILITY_VERSION_3,
+		.pid = 0,
+	};
+	struct __user_cap_data_struct data[2];
+
+	if (syscall(SYS_capget, &header, data) < 0)
+		return -1;
+	data[0].effective = 0;
+	data[1].effective = 0;
+	return syscall(SYS_capset, &header, data);
+}
+
+FN_TEST(unprivileged)
+{
+	int pid, status;
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK(drop_effective_caps());
+		CHECK_WITH(send_addr_req(sk_req, RTM_NEWADDR, NLM_F_REPLACE,
+					 lo_addr),
+			   _ret == -EPERM);
+		CHECK_WITH(send_addr_req(sk_req, RTM_DELADDR, 0, lo_addr),
+			   _ret == -EPERM);
+		exit(EXIT_SUCCESS);
+	}
+
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+	TEST_RES(count_lo_addrs(), _ret == 1);
+}
+END_TEST()
+
+// Returns the flags of the loopback interface.
+static int get_lo_flags(void)
+{
+	struct link_req req;
+	char buf[4096];
+	struct nlmsghdr *hdr = (struct nlmsghdr *)buf;
+	struct ifinfomsg *ifi = NLMSG_DATA(hdr);
+
+	memset(&req, 0, sizeof(req));
+	req.hdr.nlmsg_len = sizeof(req);
+	req.hdr.nlmsg_type = RTM_GETLINK;
+	req.hdr.nlmsg_flags = NLM_F_REQUEST;
+	req.hdr.nlmsg_seq = ++seq;
+	req.ifi.ifi_family = AF_UNSPEC;
+	req.ifi.ifi_index = lo_index;
+
+	if (send(sk_req, &req, sizeof(req), 0) != sizeof(req))
+		return -1;
+	if (recv(sk_req, buf, sizeof(buf), 0) < 0)
+		return -1;
+	if (hdr->nlmsg_type != RTM_NEWLINK || ifi->ifi_index != lo_index)
+		return -1;
+	return ifi->ifi_flags;
+}
+
+static int send_link_req(int type, unsigned int flags, unsigned int change)
+{
+	struct link_req req;
+
+	memset(&req, 0, sizeof(req));
+	req.hdr.nlmsg_len = sizeof(req);
+	req.hdr.nlmsg_type = type;
+	req.hdr.nlmsg_flags = NLM_F_REQUEST | NLM_F_ACK;
+	req.hdr.nlmsg_seq = ++seq;
+	req.ifi.ifi_family = AF_UNSPEC;
+	req.ifi.ifi_index = lo_index;
+	req.ifi.ifi_flags = flags;
+	req.ifi.ifi_change = change;
+
+	if (send(sk_req, &req, sizeof(req), 0) != sizeof(req))
+		return -1;
+	return recv_ack(sk_req, seq);
+}
+
+FN_TEST(set_link)
+{
+	int flags;
+
+	flags = TEST_RES(get_lo_flags(), _ret >= 0 && (_ret & IFF_LOOPBACK));
+
+	// Nothing is changed.
+	TEST_RES(send_link_req(RTM_SETLINK, 0, 0), _ret == 0);
+	TEST_RES(send_link_req(RTM_SETLINK, flags & IFF_UP, IFF_UP), _ret == 0);
+	TEST_RES(send_link_req(RTM_NEWLINK, flags & IFF_UP, IFF_UP), _ret == 0);
+
+	TEST_RES(get_lo_flags(), _ret == flags);
+}
+END_TEST()
@@ -0,0 +1,237 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <sys/socket.h>
+#include <sys/syscall.h>
+#include <sys/un.h>
+#include <sys/wait.h>
+#include <linux/capability.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
+#include <fcntl.h>
+#include <stddef.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "test.h"
+
+#define PORT_START_PATH "/proc/sys/net/ipv4/ip_unprivileged_port_start"
+
+static int drop_effective_caps(void)
+{
+	struct __user_cap_header_struct header = {
+		.version = _LINUX_CAPABILITY_VERSION_3,
+		.pid = 0,
+	};
+	struct __user_cap_data_struct data[2];
+
+	if (syscall(SYS_capget, &header, data) < 0)
+		return -1;
+	data[0].effective = 0;
+	data[1].effective = 0;
+	return syscall(SYS_capset, &header, data);
+}
+
+static int bind_port(int type, int port)
+{
+	struct sockaddr_in addr = {
+		.sin_family = AF_INET,
+		.sin_port = htons(port),
+		.sin_addr = { htonl(INADDR_LOOPBACK) },
+	};
+	int sk, ret;
+
+	sk = socket(PF_INET, type, 0);
+	if (sk < 0)
+		return -1;
+	ret = bind(sk, (struct sockaddr *)&addr, sizeof(addr));
+	close(sk);
+
+	return ret;
+}
+
+static int write_port_start(const char *val)
+{
+	int fd, ret;
+
+	fd = open(PORT_START_PATH, O_WRONLY);
+	if (fd < 0)
+		return -1;
+	ret = write(fd, val, strlen(val));
+	close(fd);
+
+	return ret;
+}
+
+static int read_port_start(void)
+{
+	char buf[16] = {};
+	int fd, ret;
+
+	fd = open(PORT_START_PATH, O_RDONLY);
+	if (fd < 0)
+		return -1;
+	ret = read(fd, buf, sizeof(buf) - 1);
+	close(fd);
+
+	return ret < 0 ? -1 : atoi(buf);
+}
+
+FN_TEST(bind_privileged_port)
+{
+	int pid, status;
+
+	TEST_RES(read_port_start(), _ret == 1024);
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK(drop_effective_caps());
+		CHECK_WITH(bind_port(SOCK_STREAM, 80), _ret < 0 && errno == EACCES);
+		CHECK_WITH(bind_port(SOCK_DGRAM, 1023),
+			   _ret < 0 && errno == EACCES);
+		CHECK(bind_port(SOCK_STREAM, 0));
+		CHECK(bind_port(SOCK_DGRAM, 1024));
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+
+	TEST_SUCC(bind_port(SOCK_STREAM, 80));
+	TEST_SUCC(bind_port(SOCK_DGRAM, 1023));
+}
+END_TEST()
+
+FN_TEST(unprivileged_port_start)
+{
+	int pid, status;
+
+	TEST_ERRNO(write_port_start("-1"), EINVAL);
+	TEST_ERRNO(write_port_start("32769"), EINVAL);
+	TEST_RES(read_port_start(), _ret == 1024);
+
+	TEST_SUCC(write_port_start("80"));
+	TEST_RES(read_port_start(), _ret == 80);
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK(drop_effective_caps());
+		CHECK_WITH(bind_port(SOCK_STREAM, 79), _ret < 0 && errno == EACCES);
+		CHECK(bind_port(SOCK_STREAM, 80));
+		CHECK(bind_port(SOCK_DGRAM, 1023));
+		CHECK_WITH(write_port_start("0"), _ret < 0 && errno == EPERM);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+
+	TEST_SUCC(write_port_start("1024"));
+	TEST_RES(read_port_start(), _ret == 1024);
+}
+END_TEST()
+
+static int check_peer_cred(int sk, pid_t pid, uid_t uid, gid_t gid)
+{
+	struct ucred cred;
+	socklen_t len = sizeof(cred);
+
+	if (getsockopt(sk, SOL_SOCKET, SO_PEERCRED, &cred, &len) < 0)
+		return -1;
+	if (len != sizeof(cred) || cred.pid != pid || cred.uid != uid ||
+	    cred.gid != gid) {
+		errno = EINVAL;
+		return -1;
+	}
+
+	return 0;
+}
+
+FN_TEST(peer_cred_socketpair)
+{
+	int sk[2];
+	struct ucred cred;
+	socklen_t len = sizeof(int);
+
+	TEST_SUCC(socketpair(PF_UNIX, SOCK_STREAM, 0, sk));
+
+	TEST_SUCC(check_peer_cred(sk[0], getpid(), geteuid(), getegid()));
+	TEST_SUCC(check_peer_cred(sk[1], getpid(), geteuid(), getegid()));
+
+	// The credentials are truncated if the buffer is too short.
+	memset(&cred, 0xff, sizeof(cred));
+	TEST_RES(getsockopt(sk[0], SOL_SOCKET, SO_PEERCRED, &cred, &len),
+		 len == sizeof(int) && cred.pid == getpid() &&
+			 cred.uid == (uid_t)-1);
+
+	TEST_ERRNO(setsockopt(sk[0], SOL_SOCKET, SO_PEERCRED, &cred,
+			      sizeof(cred)),
+		   ENOPROTOOPT);
+
+	TEST_SUCC(close(sk[0]));
+	TEST_SUCC(close(sk[1]));
+}
+END_TEST()
+
+FN_TEST(peer_cred_unconnected)
+{
+	int sk;
+
+	sk = TEST_SUCC(socket(PF_UNIX, SOCK_STREAM, 0));
+	TEST_SUCC(check_peer_cred(sk, 0, (uid_t)-1, (gid_t)-1));
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(peer_cred_setuid)
+{
+	struct sockaddr_un addr = {
+		.sun_family = AF_UNIX,
+		.sun_path = "\0sock_cred",
+	};
+	socklen_t addr_len = offsetof(struct sockaddr_un, sun_path) + 10;
+	uid_t uid = geteuid();
+	gid_t gid = getegid();
+	int sk_listen, sk_accept, pid, status;
+	int pipe_fds[2];
+	char byte;
+
+	sk_listen = TEST_SUCC(socket(PF_UNIX, SOCK_STREAM, 0));
+	TEST_SUCC(bind(sk_listen, (struct sockaddr *)&addr, addr_len));
+	TEST_SUCC(listen(sk_listen, 1));
+	TEST_SUCC(check_peer_cred(sk_listen, getpid(), uid, gid));
+
+	TEST_SUCC(pipe(pipe_fds));
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		int sk_connect;
+
+		sk_connect = CHECK(socket(PF_UNIX, SOCK_STREAM, 0));
+		CHECK(connect(sk_connect, (struct sockaddr *)&addr, addr_len));
+
+		// The credentials are recorded when the connection is made.
+		CHECK(setresgid(1000, 1000, 1000));
+		CHECK(setresuid(1000, 1000, 1000));
+		CHECK(check_peer_cred(sk_connect, getppid(), uid, gid));
+
+		CHECK(write(pipe_fds[1], "x", 1));
+		CHECK(read(sk_connect, &byte, 1));
+		exit(EXIT_SUCCESS);
+	}
+
+	TEST_RES(read(pipe_fds[0], &byte, 1), _ret == 1);
+
+	sk_accept = TEST_SUCC(accept(sk_listen, NULL, NULL));
+	TEST_SUCC(check_peer_cred(sk_accept, pid, uid, gid));
+
+	TEST_RES(write(sk_accept, "x", 1), _ret == 1);
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+
+	TEST_SUCC(close(sk_accept));
+	TEST_SUCC(close(sk_listen));
+	TEST_SUCC(close(pipe_fds[0]));
+	TEST_SUCC(close(pipe_fds[1]));
+}
+END_TEST()
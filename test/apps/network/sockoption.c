@@ -279,6 +279,61 @@ FN_TEST(keepidle)
 }
 END_TEST()
 
+FN_TEST(keepintvl_and_keepcnt)
+{
+	int val;
+	socklen_t val_len = sizeof(val);
+
+	// 1. Check default values
+	refresh_connection();
+	TEST_RES(getsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPINTVL, &val,
+			    &val_len),
+		 val == 75);
+	TEST_RES(getsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPCNT, &val,
+			    &val_len),
+		 val == 9);
+
+	// 2. Set and get values
+	val = 30;
+	TEST_SUCC(setsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPINTVL, &val,
+			     sizeof(val)));
+	TEST_RES(getsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPINTVL, &val,
+			    &val_len),
+		 val == 30);
+
+	val = 5;
+	TEST_SUCC(setsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPCNT, &val,
+			     sizeof(val)));
+	TEST_RES(getsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPCNT, &val,
+			    &val_len),
+		 val == 5);
+
+	// 3. Set invalid values
+	val = 0;
+	TEST_ERRNO(setsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPINTVL, &val,
+			      sizeof(val)),
+		   EINVAL);
+	TEST_ERRNO(setsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPCNT, &val,
+			      sizeof(val)),
+		   EINVAL);
+	val = 32768;
+	TEST_ERRNO(setsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPINTVL, &val,
+			      sizeof(val)),
+		   EINVAL);
+	val = 128;
+	TEST_ERRNO(setsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPCNT, &val,
+			      sizeof(val)),
+		   EINVAL);
+
+	TEST_RES(getsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPINTVL, &val,
+			    &val_len),
+		 val == 30);
+	TEST_RES(getsockopt(sk_connected, IPPROTO_TCP, TCP_KEEPCNT, &val,
+			    &val_len),
+		 val == 5);
+}
+END_TEST()
+
 FN_TEST(ip_tos)
 {
 	int tos;
@@ -0,0 +1,292 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#include <unistd.h>
+#include <fcntl.h>
+#include <sys/socket.h>
+#include <sys/poll.h>
+#include <netinet/in.h>
+#include <netinet/tcp.h>
+#include <arpa/inet.h>
+
+#include "test.h"
+
+static struct sockaddr_in sk_addr;
+
+#define S_PORT htons(0x1238)
+
+#define SOMAXCONN_PATH "/proc/sys/net/core/somaxconn"
+#define TCP_FASTOPEN_PATH "/proc/sys/net/ipv4/tcp_fastopen"
+#define TCP_MAX_TW_BUCKETS_PATH "/proc/sys/net/ipv4/tcp_max_tw_buckets"
+
+static int read_param(const char *path)
+{
+	char buf[32] = { 0 };
+	int fd;
+
+	fd = CHECK(open(path, O_RDONLY));
+	CHECK(read(fd, buf, sizeof(buf) - 1));
+	CHECK(close(fd));
+
+	return atoi(buf);
+}
+
+static int write_param(const char *path, const char *value)
+{
+	int fd, ret, err;
+
+	fd = CHECK(open(path, O_WRONLY));
+	ret = write(fd, value, strlen(value));
+	err = errno;
+	CHECK(close(fd));
+	errno = err;
+
+	return ret;
+}
+
+static int sk_listen;
+
+FN_SETUP(listen)
+{
+	sk_addr.sin_family = AF_INET;
+	sk_addr.sin_port = S_PORT;
+	CHECK(inet_aton("127.0.0.1", &sk_addr.sin_addr));
+
+	sk_listen = CHECK(socket(PF_INET, SOCK_STREAM, 0));
+	CHECK(bind(sk_listen, (struct sockaddr *)&sk_addr, sizeof(sk_addr)));
+	CHECK(listen(sk_listen, 1));
+}
+END_SETUP()
+
+static int connect_nonblocking(void)
+{
+	int sk;
+
+	sk = CHECK(socket(PF_INET, SOCK_STREAM | SOCK_NONBLOCK, 0));
+	CHECK_WITH(connect(sk, (struct sockaddr *)&sk_addr, sizeof(sk_addr)),
+		   _ret < 0 && errno == EINPROGRESS);
+
+	return sk;
+}
+
+static int is_connected(int sk, int timeout)
+{
+	struct pollfd pfd = { .fd = sk, .events = POLLOUT };
+
+	CHECK(poll(&pfd, 1, timeout));
+	return pfd.revents == POLLOUT;
+}
+
+static unsigned short local_port(int sk)
+{
+	struct sockaddr_in addr;
+	socklen_t len = sizeof(addr);
+
+	CHECK(getsockname(sk, (struct sockaddr *)&addr, &len));
+	return addr.sin_port;
+}
+
+static unsigned short peer_port(int sk)
+{
+	struct sockaddr_in addr;
+	socklen_t len = sizeof(addr);
+
+	CHECK(getpeername(sk, (struct sockaddr *)&addr, &len));
+	return addr.sin_port;
+}
+
+FN_TEST(accept_queue)
+{
+	int sk1, sk2, sk3;
+	int sk_accepted;
+
+	sk1 = connect_nonblocking();
+	TEST_RES(is_connected(sk1, 1000), _ret == 1);
+	sk2 = connect_nonblocking();
+	TEST_RES(is_connected(sk2, 1000), _ret == 1);
+
+	// The accept queue holds at most `backlog + 1` connections
+	sk3 = connect_nonblocking();
+	TEST_RES(is_connected(sk3, 100), _ret == 0);
+
+	// The connections are accepted in order
+	sk_accepted = TEST_SUCC(accept(sk_listen, NULL, NULL));
+	TEST_RES(peer_port(sk_accepted), _ret == local_port(sk1));
+	TEST_SUCC(close(sk_accepted));
+
+	sk_accepted = TEST_SUCC(accept(sk_listen, NULL, NULL));
+	TEST_RES(peer_port(sk_accepted), _ret == local_port(sk2));
+	TEST_SUCC(close(sk_accepted));
+
+	TEST_SUCC(close(sk1));
+	TEST_SUCC(close(sk2));
+	TEST_SUCC(close(sk3));
+}
+END_TEST()
+
+FN_TEST(somaxconn)
+{
+	int old_somaxconn;
+	char buf[16];
+	int sk1, sk2, sk3;
+	int sk_accepted;
+
+	old_somaxconn = read_param(SOMAXCONN_PATH);
+	TEST_RES(old_somaxconn, _ret > 0);
+
+	TEST_ERRNO(write_param(SOMAXCONN_PATH, "-1"), EINVAL);
+	TEST_SUCC(write_param(SOMAXCONN_PATH, "1"));
+	TEST_RES(read_param(SOMAXCONN_PATH), _ret == 1);
+
+	// The backlog is limited by `somaxconn`
+	TEST_SUCC(listen(sk_listen, 100));
+
+	sk1 = connect_nonblocking();
+	TEST_RES(is_connected(sk1, 1000), _ret == 1);
+	sk2 = connect_nonblocking();
+	TEST_RES(is_connected(sk2, 1000), _ret == 1);
+	sk3 = connect_nonblocking();
+	TEST_RES(is_connected(sk3, 100), _ret == 0);
+
+	sk_accepted = TEST_SUCC(accept(sk_listen, NULL, NULL));
+	TEST_SUCC(close(sk_accepted));
+	sk_accepted = TEST_SUCC(accept(sk_listen, NULL, NULL));
+	TEST_SUCC(close(sk_accepted));
+
+	TEST_SUCC(close(sk1));
+	TEST_SUCC(close(sk2));
+	TEST_SUCC(close(sk3));
+
+	snprintf(buf, sizeof(buf), "%d", old_somaxconn);
+	TEST_SUCC(write_param(SOMAXCONN_PATH, buf));
+	TEST_RES(read_param(SOMAXCONN_PATH), _ret == old_somaxconn);
+	TEST_SUCC(listen(sk_listen, 1));
+}
+END_TEST()
+
+FN_TEST(fastopen_option)
+{
+	int val;
+	socklen_t len = sizeof(val);
+	int sk;
+
+	TEST_RES(getsockopt(sk_listen, IPPROTO_TCP, TCP_FASTOPEN, &val, &len),
+		 val == 0);
+
+	val = -1;
+	TEST_ERRNO(setsockopt(sk_listen, IPPROTO_TCP, TCP_FASTOPEN, &val,
+			      sizeof(val)),
+		   EINVAL);
+
+	val = 5;
+	TEST_SUCC(setsockopt(sk_listen, IPPROTO_TCP, TCP_FASTOPEN, &val,
+			     sizeof(val)));
+	TEST_RES(getsockopt(sk_listen, IPPROTO_TCP, TCP_FASTOPEN, &val, &len),
+		 val == 5);
+
+	// TCP Fast Open cannot be enabled on connected sockets
+	sk = connect_nonblocking();
+	TEST_RES(is_connected(sk, 1000), _ret == 1);
+	TEST_ERRNO(setsockopt(sk, IPPROTO_TCP, TCP_FASTOPEN, &val, sizeof(val)),
+		   EINVAL);
+	TEST_SUCC(close(sk));
+
+	sk = TEST_SUCC(accept(sk_listen, NULL, NULL));
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(fastopen_send)
+{
+	int sk, sk_accepted;
+	char buf[16];
+
+	sk = TEST_SUCC(socket(PF_INET, SOCK_STREAM, 0));
+	TEST_RES(sendto(sk, "hello", 5, MSG_FASTOPEN,
+			(struct sockaddr *)&sk_addr, sizeof(sk_addr)),
+		 _ret == 5);
+
+	sk_accepted = TEST_SUCC(accept(sk_listen, NULL, NULL));
+	TEST_RES(recv(sk_accepted, buf, sizeof(buf), 0),
+		 _ret == 5 && memcmp(buf, "hello", 5) == 0);
+
+	// The socket is already connected
+	TEST_ERRNO(sendto(sk, "hello", 5, MSG_FASTOPEN,
+			  (struct sockaddr *)&sk_addr, sizeof(sk_addr)),
+		   EISCONN);
+
+	TEST_SUCC(close(sk_accepted));
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(fastopen_disabled)
+{
+	int old_tcp_fastopen;
+	char buf[16];
+	int sk;
+
+	old_tcp_fastopen = read_param(TCP_FASTOPEN_PATH);
+	TEST_SUCC(write_param(TCP_FASTOPEN_PATH, "0"));
+	TEST_RES(read_param(TCP_FASTOPEN_PATH), _ret == 0);
+
+	sk = TEST_SUCC(socket(PF_INET, SOCK_STREAM, 0));
+	TEST_ERRNO(sendto(sk, "hello", 5, MSG_FASTOPEN,
+			  (struct sockaddr *)&sk_addr, sizeof(sk_addr)),
+		   EOPNOTSUPP);
+	TEST_SUCC(close(sk));
+
+	snprintf(buf, sizeof(buf), "%d", old_tcp_fastopen);
+	TEST_SUCC(write_param(TCP_FASTOPEN_PATH, buf));
+	TEST_RES(read_param(TCP_FASTOPEN_PATH), _ret == old_tcp_fastopen);
+}
+END_TEST()
+
+FN_TEST(max_tw_buckets)
+{
+	int old_max_tw_buckets;
+	char buf[16];
+	struct sockaddr_in tw_addr = sk_addr;
+	int sk_tw_listen, sk_connect, sk_accepted, sk_new;
+
+	old_max_tw_buckets = read_param(TCP_MAX_TW_BUCKETS_PATH);
+	TEST_RES(old_max_tw_buckets, _ret > 0);
+
+	TEST_ERRNO(write_param(TCP_MAX_TW_BUCKETS_PATH, "-1"), EINVAL);
+	TEST_SUCC(write_param(TCP_MAX_TW_BUCKETS_PATH, "0"));
+	TEST_RES(read_param(TCP_MAX_TW_BUCKETS_PATH), _ret == 0);
+
+	tw_addr.sin_port = htons(0x1239);
+	sk_tw_listen = TEST_SUCC(socket(PF_INET, SOCK_STREAM, 0));
+	TEST_SUCC(bind(sk_tw_listen, (struct sockaddr *)&tw_addr,
+		       sizeof(tw_addr)));
+	TEST_SUCC(listen(sk_tw_listen, 1));
+
+	sk_connect = TEST_SUCC(socket(PF_INET, SOCK_STREAM, 0));
+	TEST_SUCC(connect(sk_connect, (struct sockaddr *)&tw_addr,
+			  sizeof(tw_addr)));
+	sk_accepted = TEST_SUCC(accept(sk_tw_listen, NULL, NULL));
+
+	// The server closes the connection first, so the accepted connection
+	// would enter the TIME-WAIT state if there were free buckets
+	TEST_SUCC(close(sk_accepted));
+	TEST_RES(recv(sk_connect, buf, sizeof(buf), 0), _ret == 0);
+	TEST_SUCC(close(sk_connect));
+	TEST_SUCC(close(sk_tw_listen));
+
+	// No connection occupies the port, even without `SO_REUSEADDR`
+	sk_new = TEST_SUCC(socket(PF_INET, SOCK_STREAM, 0));
+	TEST_SUCC(bind(sk_new, (struct sockaddr *)&tw_addr, sizeof(tw_addr)));
+	TEST_SUCC(close(sk_new));
+
+	snprintf(buf, sizeof(buf), "%d", old_max_tw_buckets);
+	TEST_SUCC(write_param(TCP_MAX_TW_BUCKETS_PATH, buf));
+	TEST_RES(read_param(TCP_MAX_TW_BUCKETS_PATH),
+		 _ret == old_max_tw_buckets);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_listen));
+}
+END_SETUP()
@@ -220,14 +220,11 @@ FN_TEST(bind_reuseaddr)
 
 	TEST_ERRNO(bind(sk2, psaddr, addrlen), EADDRINUSE);
 
-	// FIXME: The test will fail in Asterinas since it doesn't check
-	// if the previous socket was bound with `SO_REUSEADDR`
-	//
-	// TEST_SUCC(setsockopt(sk1, SOL_SOCKET, SO_REUSEADDR, &disable,
-	// 		     sizeof(disable)));
-	// TEST_SUCC(setsockopt(sk2, SOL_SOCKET, SO_REUSEADDR, &enable,
-	// 		     sizeof(enable)));
-	// TEST_ERRNO(bind(sk2, psaddr, addrlen), EADDRINUSE);
+	TEST_SUCC(setsockopt(sk1, SOL_SOCKET, SO_REUSEADDR, &disable,
+			     sizeof(disable)));
+	TEST_SUCC(setsockopt(sk2, SOL_SOCKET, SO_REUSEADDR, &enable,
+			     sizeof(enable)));
+	TEST_ERRNO(bind(sk2, psaddr, addrlen), EADDRINUSE);
 
 	TEST_SUCC(setsockopt(sk1, SOL_SOCKET, SO_REUSEADDR, &enable,
 			     sizeof(enable)));
@@ -649,6 +646,35 @@ FN_TEST(shutdown_shutdown)
 }
 END_TEST()
 
+FN_TEST(shutdown_half_close)
+{
+	int sk_accept;
+	int sk_connect;
+	socklen_t len;
+	struct pollfd pfd = { .events = POLLIN | POLLOUT };
+	char buf[6] = "hello";
+
+	SETUP_CONN;
+
+	// The sending half is closed, but the receiving half is still open
+	TEST_SUCC(shutdown(sk_connect, SHUT_WR));
+	TEST_ERRNO(send(sk_connect, buf, sizeof(buf), 0), EPIPE);
+	TEST_RES(recv(sk_accept, buf, sizeof(buf), 0), _ret == 0);
+
+	TEST_RES(send(sk_accept, buf, sizeof(buf), 0), _ret == sizeof(buf));
+	TEST_RES(recv(sk_connect, buf, sizeof(buf), 0),
+		 _ret == sizeof(buf) && memcmp(buf, "hello", 6) == 0);
+
+	pfd.fd = sk_connect;
+	TEST_RES(poll(&pfd, 1, 0), pfd.revents == POLLOUT);
+	pfd.fd = sk_accept;
+	TEST_RES(poll(&pfd, 1, 0), pfd.revents == (POLLIN | POLLOUT));
+
+	TEST_SUCC(close(sk_accept));
+	TEST_SUCC(close(sk_connect));
+}
+END_TEST()
+
 FN_TEST(connreset)
 {
 	int sk_accept;
@@ -730,6 +756,46 @@ END_TEST()
 
 #undef SETUP_CONN
 
+FN_TEST(shutdown_unconnected)
+{
+	int sk;
+	int sk_connect;
+
+	TEST_ERRNO(shutdown(sk_unbound, SHUT_RDWR), ENOTCONN);
+	TEST_ERRNO(shutdown(sk_bound, SHUT_WR), ENOTCONN);
+
+	sk_addr.sin_port = htons(0x4322);
+
+	sk = TEST_SUCC(socket(PF_INET, SOCK_STREAM | SOCK_NONBLOCK, 0));
+	TEST_SUCC(bind(sk, (struct sockaddr *)&sk_addr, sizeof(sk_addr)));
+	TEST_SUCC(listen(sk, 2));
+
+	// Shutting down the sending half does nothing
+	TEST_SUCC(shutdown(sk, SHUT_WR));
+	TEST_ERRNO(accept(sk, NULL, NULL), EAGAIN);
+
+	// Shutting down the receiving half stops listening
+	TEST_SUCC(shutdown(sk, SHUT_RD));
+	TEST_ERRNO(accept(sk, NULL, NULL), EINVAL);
+	TEST_ERRNO(shutdown(sk, SHUT_RDWR), ENOTCONN);
+
+	sk_connect = TEST_SUCC(socket(PF_INET, SOCK_STREAM, 0));
+	TEST_ERRNO(connect(sk_connect, (struct sockaddr *)&sk_addr,
+			   sizeof(sk_addr)),
+		   ECONNREFUSED);
+	TEST_SUCC(close(sk_connect));
+
+	// The socket is still bound, so it can listen again
+	TEST_SUCC(listen(sk, 2));
+	sk_connect = TEST_SUCC(socket(PF_INET, SOCK_STREAM, 0));
+	TEST_SUCC(connect(sk_connect, (struct sockaddr *)&sk_addr,
+			  sizeof(sk_addr)));
+	TEST_SUCC(close(sk_connect));
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
 FN_TEST(listen_close)
 {
 	int sk_listen;
@@ -754,3 +820,46 @@ FN_TEST(listen_close)
 	TEST_SUCC(close(sk_connect));
 }
 END_TEST()
+
+FN_TEST(reuseaddr_time_wait)
+{
+	int sk_listen;
+	int sk_connect;
+	int sk_accept;
+	int sk_new;
+	int enable = 1;
+	char buf[1];
+
+	sk_addr.sin_port = htons(0x4323);
+
+	sk_listen = TEST_SUCC(socket(PF_INET, SOCK_STREAM, 0));
+	TEST_SUCC(setsockopt(sk_listen, SOL_SOCKET, SO_REUSEADDR, &enable,
+			     sizeof(enable)));
+	TEST_SUCC(
+		bind(sk_listen, (struct sockaddr *)&sk_addr, sizeof(sk_addr)));
+	TEST_SUCC(listen(sk_listen, 2));
+
+	sk_connect = TEST_SUCC(socket(PF_INET, SOCK_STREAM, 0));
+	TEST_SUCC(connect(sk_connect, (struct sockaddr *)&sk_addr,
+			  sizeof(sk_addr)));
+	sk_accept = TEST_SUCC(accept(sk_listen, NULL, NULL));
+
+	// The server closes the connection first, so the accepted connection
+	// still occupies the port after the listener is closed
+	TEST_SUCC(close(sk_accept));
+	TEST_RES(recv(sk_connect, buf, sizeof(buf), 0), _ret == 0);
+	TEST_SUCC(close(sk_connect));
+	TEST_SUCC(close(sk_listen));
+
+	sk_new = TEST_SUCC(socket(PF_INET, SOCK_STREAM, 0));
+	TEST_ERRNO(bind(sk_new, (struct sockaddr *)&sk_addr, sizeof(sk_addr)),
+		   EADDRINUSE);
+
+	TEST_SUCC(setsockopt(sk_new, SOL_SOCKET, SO_REUSEADDR, &enable,
+			     sizeof(enable)));
+	TEST_SUCC(bind(sk_new, (struct sockaddr *)&sk_addr, sizeof(sk_addr)));
+	TEST_SUCC(listen(sk_new, 2));
+
+	TEST_SUCC(close(sk_new));
+}
+END_TEST()
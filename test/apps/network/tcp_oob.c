@@ -0,0 +1,209 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <unistd.h>
+#include <signal.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <sys/poll.h>
+#include <sys/epoll.h>
+#include <sys/select.h>
+#include <netinet/in.h>
+#include <netinet/tcp.h>
+#include <arpa/inet.h>
+#include <fcntl.h>
+
+#include "test.h"
+
+#define S_PORT htons(0x1243)
+
+static struct sockaddr_in sk_addr;
+static struct pollfd pfd = { .events = POLLIN | POLLPRI };
+static char buf[16];
+
+static int sk_listen;
+static int sk_connect;
+static int sk_accept;
+
+FN_SETUP(connected)
+{
+	int one = 1;
+
+	sk_addr.sin_family = AF_INET;
+	sk_addr.sin_port = S_PORT;
+	CHECK(inet_aton("127.0.0.1", &sk_addr.sin_addr));
+
+	sk_listen = CHECK(socket(PF_INET, SOCK_STREAM, 0));
+	CHECK(bind(sk_listen, (struct sockaddr *)&sk_addr, sizeof(sk_addr)));
+	CHECK(listen(sk_listen, 1));
+
+	sk_connect = CHECK(socket(PF_INET, SOCK_STREAM, 0));
+	CHECK(setsockopt(sk_connect, IPPROTO_TCP, TCP_NODELAY, &one,
+			 sizeof(one)));
+	CHECK(connect(sk_connect, (struct sockaddr *)&sk_addr,
+		      sizeof(sk_addr)));
+
+	sk_accept = CHECK(accept4(sk_listen, NULL, NULL, SOCK_NONBLOCK));
+	pfd.fd = sk_accept;
+}
+END_SETUP()
+
+FN_TEST(no_urgent_data)
+{
+	TEST_ERRNO(recv(sk_accept, buf, sizeof(buf), MSG_OOB), EINVAL);
+	TEST_RES(sockatmark(sk_accept), _ret == 0);
+	TEST_ERRNO(sockatmark(sk_listen), EINVAL);
+
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(recv_urgent)
+{
+	TEST_RES(send(sk_connect, "abc", 3, MSG_OOB), _ret == 3);
+
+	TEST_RES(poll(&pfd, 1, 0), pfd.revents == (POLLIN | POLLPRI));
+	TEST_RES(sockatmark(sk_accept), _ret == 0);
+
+	// The normal data stops at the urgent mark.
+	TEST_RES(read(sk_accept, buf, sizeof(buf)),
+		 _ret == 2 && memcmp(buf, "ab", 2) == 0);
+	TEST_RES(sockatmark(sk_accept), _ret == 1);
+
+	TEST_RES(recv(sk_accept, buf, sizeof(buf), MSG_OOB | MSG_PEEK),
+		 _ret == 1 && buf[0] == 'c');
+	TEST_RES(recv(sk_accept, buf, sizeof(buf), MSG_OOB),
+		 _ret == 1 && buf[0] == 'c');
+	TEST_ERRNO(recv(sk_accept, buf, sizeof(buf), MSG_OOB), EINVAL);
+
+	TEST_RES(poll(&pfd, 1, 0), !(pfd.revents & POLLPRI));
+	TEST_RES(sockatmark(sk_accept), _ret == 1);
+
+	// The urgent byte is not in the normal data.
+	TEST_RES(send(sk_connect, "de", 2, 0), _ret == 2);
+	TEST_RES(read(sk_accept, buf, sizeof(buf)),
+		 _ret == 2 && memcmp(buf, "de", 2) == 0);
+	TEST_RES(sockatmark(sk_accept), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(skip_unread_urgent)
+{
+	TEST_RES(send(sk_connect, "f", 1, MSG_OOB), _ret == 1);
+	TEST_RES(sockatmark(sk_accept), _ret == 1);
+
+	// The urgent byte is skipped even if it is not read out of band.
+	TEST_RES(send(sk_connect, "gh", 2, 0), _ret == 2);
+	TEST_RES(read(sk_accept, buf, sizeof(buf)),
+		 _ret == 2 && memcmp(buf, "gh", 2) == 0);
+	TEST_ERRNO(recv(sk_accept, buf, sizeof(buf), MSG_OOB), EINVAL);
+}
+END_TEST()
+
+static int has_exception(int fd)
+{
+	struct timeval tv = { 0, 0 };
+	fd_set exceptfds;
+
+	FD_ZERO(&exceptfds);
+	FD_SET(fd, &exceptfds);
+	return select(fd + 1, NULL, NULL, &exceptfds, &tv) == 1 &&
+	       FD_ISSET(fd, &exceptfds);
+}
+
+FN_TEST(exceptional_condition)
+{
+	struct epoll_event ev = { .events = EPOLLPRI };
+	int epfd;
+
+	epfd = TEST_SUCC(epoll_create1(0));
+	TEST_SUCC(epoll_ctl(epfd, EPOLL_CTL_ADD, sk_accept, &ev));
+	TEST_RES(epoll_wait(epfd, &ev, 1, 0), _ret == 0);
+
+	TEST_RES(send(sk_connect, "ij", 2, MSG_OOB), _ret == 2);
+
+	// The urgent data are reported as an exceptional condition.
+	TEST_RES(has_exception(sk_accept), _ret);
+	TEST_RES(epoll_wait(epfd, &ev, 1, 0),
+		 _ret == 1 && ev.events == EPOLLPRI);
+
+	TEST_RES(recv(sk_accept, buf, sizeof(buf), MSG_OOB),
+		 _ret == 1 && buf[0] == 'j');
+	TEST_RES(epoll_wait(epfd, &ev, 1, 0), _ret == 0);
+	TEST_RES(read(sk_accept, buf, sizeof(buf)),
+		 _ret == 1 && buf[0] == 'i');
+
+	TEST_SUCC(close(epfd));
+}
+END_TEST()
+
+FN_TEST(oob_inline)
+{
+	int one = 1;
+	int val;
+	socklen_t len = sizeof(val);
+
+	TEST_SUCC(setsockopt(sk_accept, SOL_SOCKET, SO_OOBINLINE, &one,
+			     sizeof(one)));
+	TEST_RES(getsockopt(sk_accept, SOL_SOCKET, SO_OOBINLINE, &val, &len),
+		 len == sizeof(val) && val == 1);
+
+	TEST_RES(send(sk_connect, "xyz", 3, MSG_OOB), _ret == 3);
+	TEST_RES(poll(&pfd, 1, 0), pfd.revents == (POLLIN | POLLPRI));
+	TEST_ERRNO(recv(sk_accept, buf, sizeof(buf), MSG_OOB), EINVAL);
+
+	// The normal data still stops at the urgent mark, but the urgent byte
+	// is left inline.
+	TEST_RES(read(sk_accept, buf, sizeof(buf)),
+		 _ret == 2 && memcmp(buf, "xy", 2) == 0);
+	TEST_RES(sockatmark(sk_accept), _ret == 1);
+	TEST_RES(read(sk_accept, buf, sizeof(buf)), _ret == 1 && buf[0] == 'z');
+	TEST_RES(sockatmark(sk_accept), _ret == 0);
+
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+}
+END_TEST()
+
+static volatile int sigurg_count;
+
+static void handle_sigurg(int sig)
+{
+	sigurg_count++;
+}
+
+FN_TEST(sigurg)
+{
+	signal(SIGURG, handle_sigurg);
+	TEST_SUCC(fcntl(sk_accept, F_SETOWN, getpid()));
+
+	TEST_RES(send(sk_connect, "123", 3, 0), sigurg_count == 0);
+	TEST_RES(send(sk_connect, "4", 1, MSG_OOB), sigurg_count == 1);
+	TEST_RES(read(sk_accept, buf, sizeof(buf)),
+		 _ret == 3 && memcmp(buf, "123", 3) == 0);
+	TEST_RES(read(sk_accept, buf, sizeof(buf)), _ret == 1 && buf[0] == '4');
+
+	TEST_SUCC(fcntl(sk_accept, F_SETOWN, 0));
+	signal(SIGURG, SIG_DFL);
+}
+END_TEST()
+
+FN_TEST(udp_oob)
+{
+	int sk_udp = CHECK(socket(PF_INET, SOCK_DGRAM, 0));
+
+	TEST_ERRNO(sendto(sk_udp, "a", 1, MSG_OOB, (struct sockaddr *)&sk_addr,
+			  sizeof(sk_addr)),
+		   EOPNOTSUPP);
+
+	TEST_SUCC(close(sk_udp));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_accept));
+	CHECK(close(sk_connect));
+	CHECK(close(sk_listen));
+}
+END_SETUP()
@@ -0,0 +1,414 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#include <linux/bpf.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
+#include <stdint.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#include "test.h"
+
+#ifndef SO_ATTACH_BPF
+#define SO_ATTACH_BPF 50
+#endif
+#ifndef SO_DETACH_BPF
+#define SO_DETACH_BPF 27
+#endif
+
+#define INSN(CODE, DST, SRC, OFF, IMM)                                   \
+	((struct bpf_insn){ .code = (CODE),                              \
+			    .dst_reg = (DST),                            \
+			    .src_reg = (SRC),                            \
+			    .off = (OFF),                                \
+			    .imm = (IMM) })
+#define MOV64_IMM(DST, IMM) INSN(BPF_ALU64 | BPF_MOV | BPF_K, DST, 0, 0, IMM)
+#define MOV64_REG(DST, SRC) INSN(BPF_ALU64 | BPF_MOV | BPF_X, DST, SRC, 0, 0)
+#define ADD64_IMM(DST, IMM) INSN(BPF_ALU64 | BPF_ADD | BPF_K, DST, 0, 0, IMM)
+#define ST_MEM(SIZE, DST, OFF, IMM) \
+	INSN(BPF_ST | BPF_MEM | (SIZE), DST, 0, OFF, IMM)
+#define ATOMIC_ADD(SIZE, DST, SRC, OFF) \
+	INSN(BPF_STX | BPF_ATOMIC | (SIZE), DST, SRC, OFF, BPF_ADD)
+#define LD_ABS(SIZE, IMM) INSN(BPF_LD | BPF_ABS | (SIZE), 0, 0, 0, IMM)
+#define LD_MAP_FD(DST, FD)                                     \
+	INSN(BPF_LD | BPF_IMM | BPF_DW, DST, BPF_PSEUDO_MAP_FD, 0, FD), \
+		INSN(0, 0, 0, 0, 0)
+#define JEQ_IMM(DST, IMM, OFF) INSN(BPF_JMP | BPF_JEQ | BPF_K, DST, 0, OFF, IMM)
+#define JA(OFF) INSN(BPF_JMP | BPF_JA, 0, 0, OFF, 0)
+#define CALL(FUNC) INSN(BPF_JMP | BPF_CALL, 0, 0, 0, FUNC)
+#define EXIT() INSN(BPF_JMP | BPF_EXIT, 0, 0, 0, 0)
+
+static int sys_bpf(int cmd, union bpf_attr *attr)
+{
+	return syscall(SYS_bpf, cmd, attr, sizeof(*attr));
+}
+
+static int map_create(int type, int key_size, int value_size, int max_entries)
+{
+	union bpf_attr attr;
+
+	memset(&attr, 0, sizeof(attr));
+	attr.map_type = type;
+	attr.key_size = key_size;
+	attr.value_size = value_size;
+	attr.max_entries = max_entries;
+
+	return sys_bpf(BPF_MAP_CREATE, &attr);
+}
+
+static int map_update(int fd, const void *key, const void *value, int flags)
+{
+	union bpf_attr attr;
+
+	memset(&attr, 0, sizeof(attr));
+	attr.map_fd = fd;
+	attr.key = (uintptr_t)key;
+	attr.value = (uintptr_t)value;
+	attr.flags = flags;
+
+	return sys_bpf(BPF_MAP_UPDATE_ELEM, &attr);
+}
+
+static int map_lookup(int fd, const void *key, void *value)
+{
+	union bpf_attr attr;
+
+	memset(&attr, 0, sizeof(attr));
+	attr.map_fd = fd;
+	attr.key = (uintptr_t)key;
+	attr.value = (uintptr_t)value;
+
+	return sys_bpf(BPF_MAP_LOOKUP_ELEM, &attr);
+}
+
+static int map_delete(int fd, const void *key)
+{
+	union bpf_attr attr;
+
+	memset(&attr, 0, sizeof(attr));
+	attr.map_fd = fd;
+	attr.key = (uintptr_t)key;
+
+	return sys_bpf(BPF_MAP_DELETE_ELEM, &attr);
+}
+
+static int map_next_key(int fd, const void *key, void *next_key)
+{
+	union bpf_attr attr;
+
+	memset(&attr, 0, sizeof(attr));
+	attr.map_fd = fd;
+	attr.key = (uintptr_t)key;
+	attr.next_key = (uintptr_t)next_key;
+
+	return sys_bpf(BPF_MAP_GET_NEXT_KEY, &attr);
+}
+
+static int prog_load(int type, const struct bpf_insn *insns, int insn_cnt)
+{
+	union bpf_attr attr;
+
+	memset(&attr, 0, sizeof(attr));
+	attr.prog_type = type;
+	attr.insns = (uintptr_t)insns;
+	attr.insn_cnt = insn_cnt;
+	attr.license = (uintptr_t) "GPL";
+
+	return sys_bpf(BPF_PROG_LOAD, &attr);
+}
+
+#define PROG_LOAD(TYPE, INSNS) \
+	prog_load(TYPE, INSNS, sizeof(INSNS) / sizeof(struct bpf_insn))
+
+FN_TEST(invalid_cmd)
+{
+	union bpf_attr attr;
+
+	memset(&attr, 0, sizeof(attr));
+	TEST_ERRNO(sys_bpf(-1, &attr), EINVAL);
+}
+END_TEST()
+
+FN_TEST(hash_map)
+{
+	int fd;
+	uint32_t key, next_key;
+	uint64_t value;
+
+	TEST_ERRNO(map_create(BPF_MAP_TYPE_HASH, 0, 8, 2), EINVAL);
+	TEST_ERRNO(map_create(BPF_MAP_TYPE_HASH, 4, 8, 0), EINVAL);
+	fd = TEST_SUCC(map_create(BPF_MAP_TYPE_HASH, 4, 8, 2));
+
+	key = 1;
+	value = 100;
+	TEST_ERRNO(map_lookup(fd, &key, &value), ENOENT);
+	TEST_ERRNO(map_update(fd, &key, &value, BPF_EXIST), ENOENT);
+	TEST_SUCC(map_update(fd, &key, &value, BPF_NOEXIST));
+	TEST_ERRNO(map_update(fd, &key, &value, BPF_NOEXIST), EEXIST);
+	value = 0;
+	TEST_RES(map_lookup(fd, &key, &value), value == 100);
+
+	key = 2;
+	value = 200;
+	TEST_SUCC(map_update(fd, &key, &value, BPF_ANY));
+	key = 3;
+	TEST_ERRNO(map_update(fd, &key, &value, BPF_ANY), E2BIG);
+
+	next_key = 0;
+	TEST_RES(map_next_key(fd, NULL, &next_key),
+		 next_key == 1 || next_key == 2);
+	key = next_key;
+	TEST_RES(map_next_key(fd, &key, &next_key),
+		 next_key == 3 - key);
+	key = next_key;
+	TEST_ERRNO(map_next_key(fd, &key, &next_key), ENOENT);
+
+	key = 1;
+	TEST_SUCC(map_delete(fd, &key));
+	TEST_ERRNO(map_delete(fd, &key), ENOENT);
+	TEST_ERRNO(map_lookup(fd, &key, &value), ENOENT);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(array_map)
+{
+	int fd;
+	uint32_t key, next_key;
+	uint64_t value;
+
+	TEST_ERRNO(map_create(BPF_MAP_TYPE_ARRAY, 8, 8, 2), EINVAL);
+	fd = TEST_SUCC(map_create(BPF_MAP_TYPE_ARRAY, 4, 8, 2));
+
+	key = 1;
+	value = 1;
+	TEST_RES(map_lookup(fd, &key, &value), value == 0);
+	value = 100;
+	TEST_SUCC(map_update(fd, &key, &value, BPF_ANY));
+	TEST_ERRNO(map_update(fd, &key, &value, BPF_NOEXIST), EEXIST);
+	value = 0;
+	TEST_RES(map_lookup(fd, &key, &value), value == 100);
+
+	key = 2;
+	TEST_ERRNO(map_update(fd, &key, &value, BPF_ANY), E2BIG);
+	TEST_ERRNO(map_lookup(fd, &key, &value), ENOENT);
+	key = 0;
+	TEST_ERRNO(map_delete(fd, &key), EINVAL);
+
+	TEST_RES(map_next_key(fd, NULL, &next_key), next_key == 0);
+	key = 0;
+	TEST_RES(map_next_key(fd, &key, &next_key), next_key == 1);
+	key = 1;
+	TEST_ERRNO(map_next_key(fd, &key, &next_key), ENOENT);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(verifier)
+{
+	struct bpf_insn infinite_loop[] = {
+		MOV64_IMM(BPF_REG_0, 0),
+		JA(-1),
+		EXIT(),
+	};
+	struct bpf_insn no_exit[] = {
+		MOV64_IMM(BPF_REG_0, 0),
+	};
+	struct bpf_insn write_fp[] = {
+		MOV64_IMM(BPF_REG_10, 0),
+		MOV64_IMM(BPF_REG_0, 0),
+		EXIT(),
+	};
+	struct bpf_insn bad_map_fd[] = {
+		LD_MAP_FD(BPF_REG_1, 0),
+		MOV64_IMM(BPF_REG_0, 0),
+		EXIT(),
+	};
+	struct bpf_insn ok[] = {
+		MOV64_IMM(BPF_REG_0, 0),
+		EXIT(),
+	};
+	int fd;
+
+	TEST_ERRNO(PROG_LOAD(BPF_PROG_TYPE_SOCKET_FILTER, infinite_loop),
+		   EINVAL);
+	TEST_ERRNO(PROG_LOAD(BPF_PROG_TYPE_SOCKET_FILTER, no_exit), EINVAL);
+	TEST_ERRNO(PROG_LOAD(BPF_PROG_TYPE_SOCKET_FILTER, write_fp), EACCES);
+	TEST_ERRNO(PROG_LOAD(BPF_PROG_TYPE_SOCKET_FILTER, bad_map_fd), EINVAL);
+	TEST_ERRNO(prog_load(BPF_PROG_TYPE_SOCKET_FILTER, ok, 0), E2BIG);
+	fd = TEST_SUCC(PROG_LOAD(BPF_PROG_TYPE_SOCKET_FILTER, ok));
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+static int sk_recv;
+static int sk_send;
+static struct sockaddr_in sk_addr;
+
+FN_SETUP(sockets)
+{
+	socklen_t addrlen = sizeof(sk_addr);
+
+	sk_recv = CHECK(socket(PF_INET, SOCK_DGRAM | SOCK_NONBLOCK, 0));
+	sk_send = CHECK(socket(PF_INET, SOCK_DGRAM | SOCK_NONBLOCK, 0));
+
+	sk_addr.sin_family = AF_INET;
+	sk_addr.sin_port = 0;
+	CHECK(inet_aton("127.0.0.1", &sk_addr.sin_addr));
+	CHECK(bind(sk_recv, (struct sockaddr *)&sk_addr, sizeof(sk_addr)));
+	CHECK(getsockname(sk_recv, (struct sockaddr *)&sk_addr, &addrlen));
+}
+END_SETUP()
+
+static int send_msg(const char *msg)
+{
+	return sendto(sk_send, msg, strlen(msg), 0, (struct sockaddr *)&sk_addr,
+		      sizeof(sk_addr));
+}
+
+static int attach_prog(int sk, int prog_fd)
+{
+	return setsockopt(sk, SOL_SOCKET, SO_ATTACH_BPF, &prog_fd,
+			  sizeof(prog_fd));
+}
+
+static int detach_prog(int sk)
+{
+	int dummy = 0;
+
+	return setsockopt(sk, SOL_SOCKET, SO_DETACH_BPF, &dummy, sizeof(dummy));
+}
+
+FN_TEST(socket_filter)
+{
+	// Keeps the UDP header and the first four bytes of the payload.
+	struct bpf_insn truncate[] = {
+		MOV64_IMM(BPF_REG_0, 8 + 4),
+		EXIT(),
+	};
+	// Drops the packets whose payloads start with 'x'.
+	struct bpf_insn drop_x[] = {
+		MOV64_REG(BPF_REG_6, BPF_REG_1),
+		LD_ABS(BPF_B, 8),
+		JEQ_IMM(BPF_REG_0, 'x', 2),
+		MOV64_IMM(BPF_REG_0, 0xffff),
+		EXIT(),
+		MOV64_IMM(BPF_REG_0, 0),
+		EXIT(),
+	};
+	struct bpf_insn tracing[] = {
+		MOV64_IMM(BPF_REG_0, 0),
+		EXIT(),
+	};
+	int truncate_fd, drop_x_fd, tracing_fd, map_fd;
+	char buf[16];
+
+	truncate_fd =
+		TEST_SUCC(PROG_LOAD(BPF_PROG_TYPE_SOCKET_FILTER, truncate));
+	drop_x_fd = TEST_SUCC(PROG_LOAD(BPF_PROG_TYPE_SOCKET_FILTER, drop_x));
+	tracing_fd = TEST_SUCC(PROG_LOAD(BPF_PROG_TYPE_RAW_TRACEPOINT, tracing));
+	map_fd = TEST_SUCC(map_create(BPF_MAP_TYPE_ARRAY, 4, 8, 1));
+
+	TEST_ERRNO(detach_prog(sk_recv), ENOENT);
+	TEST_ERRNO(attach_prog(sk_recv, map_fd), EINVAL);
+	TEST_ERRNO(attach_prog(sk_recv, tracing_fd), EINVAL);
+	TEST_ERRNO(attach_prog(sk_recv, -1), EBADF);
+
+	TEST_SUCC(attach_prog(sk_recv, truncate_fd));
+	TEST_RES(send_msg("hello"), _ret == 5);
+	TEST_RES(recv(sk_recv, buf, sizeof(buf), 0),
+		 _ret == 4 && memcmp(buf, "hell", 4) == 0);
+
+	TEST_SUCC(attach_prog(sk_recv, drop_x_fd));
+	TEST_RES(send_msg("xyz"), _ret == 3);
+	TEST_RES(send_msg("abc"), _ret == 3);
+	TEST_RES(recv(sk_recv, buf, sizeof(buf), 0),
+		 _ret == 3 && memcmp(buf, "abc", 3) == 0);
+	TEST_ERRNO(recv(sk_recv, buf, sizeof(buf), 0), EAGAIN);
+
+	TEST_SUCC(detach_prog(sk_recv));
+	TEST_ERRNO(detach_prog(sk_recv), ENOENT);
+	TEST_RES(send_msg("xyz"), _ret == 3);
+	TEST_RES(recv(sk_recv, buf, sizeof(buf), 0),
+		 _ret == 3 && memcmp(buf, "xyz", 3) == 0);
+
+	TEST_SUCC(close(truncate_fd));
+	TEST_SUCC(close(drop_x_fd));
+	TEST_SUCC(close(tracing_fd));
+	TEST_SUCC(close(map_fd));
+}
+END_TEST()
+
+FN_TEST(socket_filter_map)
+{
+	int map_fd, prog_fd;
+	uint32_t key = 0;
+	uint64_t value;
+	char buf[16];
+
+	map_fd = TEST_SUCC(map_create(BPF_MAP_TYPE_ARRAY, 4, 8, 1));
+
+	// Counts the packets in the map.
+	struct bpf_insn count[] = {
+		ST_MEM(BPF_W, BPF_REG_10, -4, 0),
+		MOV64_REG(BPF_REG_2, BPF_REG_10),
+		ADD64_IMM(BPF_REG_2, -4),
+		LD_MAP_FD(BPF_REG_1, map_fd),
+		CALL(BPF_FUNC_map_lookup_elem),
+		JEQ_IMM(BPF_REG_0, 0, 2),
+		MOV64_IMM(BPF_REG_1, 1),
+		ATOMIC_ADD(BPF_DW, BPF_REG_0, BPF_REG_1, 0),
+		MOV64_IMM(BPF_REG_0, 0xffff),
+		EXIT(),
+	};
+	prog_fd = TEST_SUCC(PROG_LOAD(BPF_PROG_TYPE_SOCKET_FILTER, count));
+
+	// The program is still referenced by the socket.
+	TEST_SUCC(attach_prog(sk_recv, prog_fd));
+	TEST_SUCC(close(prog_fd));
+
+	TEST_RES(send_msg("a"), _ret == 1);
+	TEST_RES(send_msg("b"), _ret == 1);
+	TEST_RES(recv(sk_recv, buf, sizeof(buf), 0), _ret == 1);
+	TEST_RES(recv(sk_recv, buf, sizeof(buf), 0), _ret == 1);
+
+	TEST_SUCC(detach_prog(sk_recv));
+	TEST_RES(map_lookup(map_fd, &key, &value), value == 2);
+
+	TEST_SUCC(close(map_fd));
+}
+END_TEST()
+
+FN_TEST(raw_tracepoint)
+{
+	struct bpf_insn tracing[] = {
+		MOV64_IMM(BPF_REG_0, 0),
+		EXIT(),
+	};
+	struct bpf_insn filter[] = {
+		MOV64_IMM(BPF_REG_0, 0),
+		EXIT(),
+	};
+	union bpf_attr attr;
+	int tracing_fd, filter_fd;
+
+	tracing_fd = TEST_SUCC(PROG_LOAD(BPF_PROG_TYPE_RAW_TRACEPOINT, tracing));
+	filter_fd = TEST_SUCC(PROG_LOAD(BPF_PROG_TYPE_SOCKET_FILTER, filter));
+
+	memset(&attr, 0, sizeof(attr));
+	attr.raw_tracepoint.name = (uintptr_t) "no_such_tracepoint";
+	attr.raw_tracepoint.prog_fd = tracing_fd;
+	TEST_ERRNO(sys_bpf(BPF_RAW_TRACEPOINT_OPEN, &attr), ENOENT);
+
+	attr.raw_tracepoint.name = (uintptr_t) "sched_switch";
+	attr.raw_tracepoint.prog_fd = filter_fd;
+	TEST_ERRNO(sys_bpf(BPF_RAW_TRACEPOINT_OPEN, &attr), EINVAL);
+
+	TEST_SUCC(close(tracing_fd));
+	TEST_SUCC(close(filter_fd));
+}
+END_TEST()
@@ -0,0 +1,175 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <unistd.h>
+#include <time.h>
+#include <poll.h>
+#include <sys/socket.h>
+#include <sys/select.h>
+#include <sys/epoll.h>
+#include <sys/wait.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
+
+#include "test.h"
+
+static struct sockaddr_in sk_addr;
+
+#define S_PORT htons(0x1238)
+#define C_PORT htons(0x1239)
+
+static int sk_server;
+static int sk_client;
+
+FN_SETUP(sockets)
+{
+	sk_addr.sin_family = AF_INET;
+	CHECK(inet_aton("127.0.0.1", &sk_addr.sin_addr));
+
+	sk_server = CHECK(socket(PF_INET, SOCK_DGRAM, 0));
+	sk_addr.sin_port = S_PORT;
+	CHECK(bind(sk_server, (struct sockaddr *)&sk_addr, sizeof(sk_addr)));
+
+	sk_client = CHECK(socket(PF_INET, SOCK_DGRAM, 0));
+	sk_addr.sin_port = C_PORT;
+	CHECK(bind(sk_client, (struct sockaddr *)&sk_addr, sizeof(sk_addr)));
+}
+END_SETUP()
+
+static int send_to_server(void)
+{
+	sk_addr.sin_port = S_PORT;
+	return sendto(sk_client, "hello", 5, 0, (struct sockaddr *)&sk_addr,
+		      sizeof(sk_addr));
+}
+
+static long long now_ns(void)
+{
+	struct timespec ts;
+
+	clock_gettime(CLOCK_MONOTONIC, &ts);
+	return ts.tv_sec * 1000000000LL + ts.tv_nsec;
+}
+
+FN_TEST(busy_poll_option)
+{
+	int val;
+	socklen_t len = sizeof(val);
+
+	TEST_RES(getsockopt(sk_server, SOL_SOCKET, SO_BUSY_POLL, &val, &len),
+		 val == 0 && len == sizeof(val));
+
+	val = 50;
+	TEST_SUCC(setsockopt(sk_server, SOL_SOCKET, SO_BUSY_POLL, &val,
+			     sizeof(val)));
+	TEST_RES(getsockopt(sk_server, SOL_SOCKET, SO_BUSY_POLL, &val, &len),
+		 val == 50);
+
+	val = -1;
+	TEST_ERRNO(setsockopt(sk_server, SOL_SOCKET, SO_BUSY_POLL, &val,
+			      sizeof(val)),
+		   EINVAL);
+	TEST_RES(getsockopt(sk_server, SOL_SOCKET, SO_BUSY_POLL, &val, &len),
+		 val == 50);
+}
+END_TEST()
+
+FN_TEST(busy_poll_recv)
+{
+	char buf[16];
+	int val;
+	pid_t pid;
+	int status;
+
+	// Data that arrive during busy polling
+	val = 100000;
+	TEST_SUCC(setsockopt(sk_server, SOL_SOCKET, SO_BUSY_POLL, &val,
+			     sizeof(val)));
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		usleep(10 * 1000);
+		CHECK(send_to_server());
+		_exit(0);
+	}
+	TEST_RES(recv(sk_server, buf, sizeof(buf), 0),
+		 _ret == 5 && memcmp(buf, "hello", 5) == 0);
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+
+	// Data that arrive after busy polling
+	val = 1;
+	TEST_SUCC(setsockopt(sk_server, SOL_SOCKET, SO_BUSY_POLL, &val,
+			     sizeof(val)));
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		usleep(100 * 1000);
+		CHECK(send_to_server());
+		_exit(0);
+	}
+	TEST_RES(recv(sk_server, buf, sizeof(buf), 0),
+		 _ret == 5 && memcmp(buf, "hello", 5) == 0);
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+
+	val = 0;
+	TEST_SUCC(setsockopt(sk_server, SOL_SOCKET, SO_BUSY_POLL, &val,
+			     sizeof(val)));
+}
+END_TEST()
+
+// The timeouts below are not multiples of a millisecond (except for `epoll_wait`). The polling
+// system calls should not return before the timeouts expire.
+
+FN_TEST(ppoll_timeout)
+{
+	struct pollfd pfd = { .fd = sk_server, .events = POLLIN };
+	struct timespec timeout = { .tv_sec = 0, .tv_nsec = 2500 * 1000 };
+	long long start;
+
+	start = now_ns();
+	TEST_RES(ppoll(&pfd, 1, &timeout, NULL),
+		 _ret == 0 && now_ns() - start >= 2500 * 1000);
+}
+END_TEST()
+
+FN_TEST(select_timeout)
+{
+	fd_set rfds;
+	struct timeval timeout = { .tv_sec = 0, .tv_usec = 2500 };
+	long long start;
+
+	FD_ZERO(&rfds);
+	FD_SET(sk_server, &rfds);
+
+	start = now_ns();
+	TEST_RES(select(sk_server + 1, &rfds, NULL, NULL, &timeout),
+		 _ret == 0 && now_ns() - start >= 2500 * 1000);
+}
+END_TEST()
+
+FN_TEST(epoll_timeout)
+{
+	int epfd;
+	struct epoll_event ev = { .events = EPOLLIN };
+	long long start;
+
+	epfd = TEST_SUCC(epoll_create1(0));
+	TEST_SUCC(epoll_ctl(epfd, EPOLL_CTL_ADD, sk_server, &ev));
+
+	start = now_ns();
+	TEST_RES(epoll_wait(epfd, &ev, 1, 3),
+		 _ret == 0 && now_ns() - start >= 3 * 1000 * 1000);
+
+	TEST_SUCC(close(epfd));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_server));
+	CHECK(close(sk_client));
+}
+END_SETUP()
@@ -0,0 +1,291 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#include <unistd.h>
+#include <time.h>
+#include <sys/socket.h>
+#include <sys/poll.h>
+#include <sys/time.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
+#include <linux/errqueue.h>
+
+#include "test.h"
+
+static struct sockaddr_in sk_addr;
+
+#define S_PORT htons(0x1235)
+#define C_PORT htons(0x1236)
+#define U_PORT htons(0x1237)
+
+static int sk_server;
+static int sk_client;
+
+FN_SETUP(sockets)
+{
+	sk_addr.sin_family = AF_INET;
+	CHECK(inet_aton("127.0.0.1", &sk_addr.sin_addr));
+
+	sk_server = CHECK(socket(PF_INET, SOCK_DGRAM | SOCK_NONBLOCK, 0));
+	sk_addr.sin_port = S_PORT;
+	CHECK(bind(sk_server, (struct sockaddr *)&sk_addr, sizeof(sk_addr)));
+
+	sk_client = CHECK(socket(PF_INET, SOCK_DGRAM | SOCK_NONBLOCK, 0));
+	sk_addr.sin_port = C_PORT;
+	CHECK(bind(sk_client, (struct sockaddr *)&sk_addr, sizeof(sk_addr)));
+}
+END_SETUP()
+
+static char msg_buf[32];
+static char cmsg_buf[256];
+static struct sockaddr_in msg_addr;
+
+static struct msghdr new_msghdr(struct iovec *iov)
+{
+	struct msghdr msg = { 0 };
+
+	iov->iov_base = msg_buf;
+	iov->iov_len = sizeof(msg_buf);
+
+	msg.msg_name = &msg_addr;
+	msg.msg_namelen = sizeof(msg_addr);
+	msg.msg_iov = iov;
+	msg.msg_iovlen = 1;
+	msg.msg_control = cmsg_buf;
+	msg.msg_controllen = sizeof(cmsg_buf);
+
+	return msg;
+}
+
+static int send_to_server(void)
+{
+	sk_addr.sin_port = S_PORT;
+	return sendto(sk_client, "hello", 5, 0, (struct sockaddr *)&sk_addr,
+		      sizeof(sk_addr));
+}
+
+static long long now_ns(void)
+{
+	struct timespec ts;
+
+	clock_gettime(CLOCK_REALTIME, &ts);
+	return ts.tv_sec * 1000000000LL + ts.tv_nsec;
+}
+
+FN_TEST(timestamp_options)
+{
+	int val;
+	socklen_t len = sizeof(val);
+
+	TEST_RES(getsockopt(sk_server, SOL_SOCKET, SO_TIMESTAMP, &val, &len),
+		 val == 0);
+
+	val = 1;
+	TEST_SUCC(setsockopt(sk_server, SOL_SOCKET, SO_TIMESTAMPNS, &val,
+			     sizeof(val)));
+	TEST_RES(getsockopt(sk_server, SOL_SOCKET, SO_TIMESTAMPNS, &val, &len),
+		 val == 1);
+
+	// Enabling `SO_TIMESTAMP` overrides `SO_TIMESTAMPNS`
+	val = 1;
+	TEST_SUCC(setsockopt(sk_server, SOL_SOCKET, SO_TIMESTAMP, &val,
+			     sizeof(val)));
+	TEST_RES(getsockopt(sk_server, SOL_SOCKET, SO_TIMESTAMPNS, &val, &len),
+		 val == 0);
+	TEST_RES(getsockopt(sk_server, SOL_SOCKET, SO_TIMESTAMP, &val, &len),
+		 val == 1);
+
+	val = 0;
+	TEST_SUCC(setsockopt(sk_server, SOL_SOCKET, SO_TIMESTAMP, &val,
+			     sizeof(val)));
+	TEST_RES(getsockopt(sk_server, SOL_SOCKET, SO_TIMESTAMP, &val, &len),
+		 val == 0);
+}
+END_TEST()
+
+FN_TEST(recv_without_timestamp)
+{
+	struct iovec iov;
+	struct msghdr msg = new_msghdr(&iov);
+
+	TEST_SUCC(send_to_server());
+	TEST_RES(recvmsg(sk_server, &msg, 0),
+		 _ret == 5 && msg.msg_controllen == 0 && msg.msg_flags == 0);
+}
+END_TEST()
+
+FN_TEST(recv_timestamp)
+{
+	struct iovec iov;
+	struct msghdr msg = new_msghdr(&iov);
+	struct cmsghdr *cmsg;
+	struct timeval tv;
+	long long before, after, time;
+	int val = 1;
+
+	TEST_SUCC(setsockopt(sk_server, SOL_SOCKET, SO_TIMESTAMP, &val,
+			     sizeof(val)));
+
+	before = now_ns() / 1000;
+	TEST_SUCC(send_to_server());
+	after = now_ns() / 1000;
+
+	TEST_RES(recvmsg(sk_server, &msg, 0), _ret == 5);
+
+	cmsg = CMSG_FIRSTHDR(&msg);
+	TEST_RES(0, cmsg != NULL && cmsg->cmsg_level == SOL_SOCKET &&
+			    cmsg->cmsg_type == SCM_TIMESTAMP &&
+			    cmsg->cmsg_len == CMSG_LEN(sizeof(tv)));
+	memcpy(&tv, CMSG_DATA(cmsg), sizeof(tv));
+	time = tv.tv_sec * 1000000LL + tv.tv_usec;
+	TEST_RES(0, before <= time && time <= after);
+}
+END_TEST()
+
+FN_TEST(recv_timestamp_ns)
+{
+	struct iovec iov;
+	struct msghdr msg = new_msghdr(&iov);
+	struct cmsghdr *cmsg;
+	struct timespec ts;
+	long long before, after, time;
+	int val = 1;
+
+	TEST_SUCC(setsockopt(sk_server, SOL_SOCKET, SO_TIMESTAMPNS, &val,
+			     sizeof(val)));
+
+	before = now_ns();
+	TEST_SUCC(send_to_server());
+	after = now_ns();
+
+	TEST_RES(recvmsg(sk_server, &msg, 0), _ret == 5);
+
+	cmsg = CMSG_FIRSTHDR(&msg);
+	TEST_RES(0, cmsg != NULL && cmsg->cmsg_level == SOL_SOCKET &&
+			    cmsg->cmsg_type == SCM_TIMESTAMPNS &&
+			    cmsg->cmsg_len == CMSG_LEN(sizeof(ts)));
+	memcpy(&ts, CMSG_DATA(cmsg), sizeof(ts));
+	time = ts.tv_sec * 1000000000LL + ts.tv_nsec;
+	TEST_RES(0, before <= time && time <= after);
+
+	val = 0;
+	TEST_SUCC(setsockopt(sk_server, SOL_SOCKET, SO_TIMESTAMPNS, &val,
+			     sizeof(val)));
+}
+END_TEST()
+
+FN_TEST(recv_err_option)
+{
+	int val;
+	socklen_t len = sizeof(val);
+
+	TEST_RES(getsockopt(sk_client, SOL_IP, IP_RECVERR, &val, &len),
+		 val == 0);
+
+	val = 1;
+	TEST_SUCC(setsockopt(sk_client, SOL_IP, IP_RECVERR, &val, sizeof(val)));
+	TEST_RES(getsockopt(sk_client, SOL_IP, IP_RECVERR, &val, &len),
+		 val == 1);
+}
+END_TEST()
+
+FN_TEST(empty_error_queue)
+{
+	struct iovec iov;
+	struct msghdr msg = new_msghdr(&iov);
+
+	TEST_ERRNO(recvmsg(sk_client, &msg, MSG_ERRQUEUE), EAGAIN);
+	TEST_ERRNO(recvmsg(sk_server, &msg, MSG_ERRQUEUE), EAGAIN);
+}
+END_TEST()
+
+FN_TEST(recv_error_queue)
+{
+	struct iovec iov;
+	struct msghdr msg = new_msghdr(&iov);
+	struct cmsghdr *cmsg;
+	struct sock_extended_err *ee;
+	struct sockaddr_in *offender;
+	struct pollfd pfd = { .fd = sk_client, .events = POLLIN };
+	int err;
+	socklen_t len = sizeof(err);
+
+	// No one is listening on the port, so an ICMP port unreachable message is generated
+	sk_addr.sin_port = U_PORT;
+	TEST_RES(sendto(sk_client, "unreachable", 11, 0,
+			(struct sockaddr *)&sk_addr, sizeof(sk_addr)),
+		 _ret == 11);
+
+	TEST_RES(poll(&pfd, 1, 0), pfd.revents == POLLERR);
+
+	TEST_RES(recvmsg(sk_client, &msg, MSG_ERRQUEUE),
+		 _ret == 11 && msg.msg_flags == MSG_ERRQUEUE &&
+			 msg_addr.sin_port == U_PORT &&
+			 memcmp(msg_buf, "unreachable", 11) == 0);
+
+	cmsg = CMSG_FIRSTHDR(&msg);
+	TEST_RES(0, cmsg != NULL && cmsg->cmsg_level == SOL_IP &&
+			    cmsg->cmsg_type == IP_RECVERR);
+	ee = (struct sock_extended_err *)CMSG_DATA(cmsg);
+	offender = (struct sockaddr_in *)SO_EE_OFFENDER(ee);
+	TEST_RES(0, ee->ee_errno == ECONNREFUSED &&
+			    ee->ee_origin == SO_EE_ORIGIN_ICMP &&
+			    ee->ee_type == 3 && ee->ee_code == 3 &&
+			    offender->sin_family == AF_INET &&
+			    offender->sin_addr.s_addr == sk_addr.sin_addr.s_addr);
+
+	// The pending error is cleared after the error queue becomes empty
+	TEST_RES(getsockopt(sk_client, SOL_SOCKET, SO_ERROR, &err, &len),
+		 err == 0);
+	TEST_RES(poll(&pfd, 1, 0), pfd.revents == 0);
+	TEST_ERRNO(recvmsg(sk_client, &msg, MSG_ERRQUEUE), EAGAIN);
+}
+END_TEST()
+
+FN_TEST(recv_error_truncated)
+{
+	struct iovec iov;
+	struct msghdr msg = new_msghdr(&iov);
+
+	sk_addr.sin_port = U_PORT;
+	TEST_RES(sendto(sk_client, "unreachable", 11, 0,
+			(struct sockaddr *)&sk_addr, sizeof(sk_addr)),
+		 _ret == 11);
+
+	iov.iov_len = 2;
+	TEST_RES(recvmsg(sk_client, &msg, MSG_ERRQUEUE),
+		 _ret == 2 && msg.msg_flags == (MSG_ERRQUEUE | MSG_TRUNC));
+}
+END_TEST()
+
+FN_TEST(recv_error_disabled)
+{
+	struct iovec iov;
+	struct msghdr msg = new_msghdr(&iov);
+	int val = 0;
+	int err;
+	socklen_t len = sizeof(err);
+
+	sk_addr.sin_port = U_PORT;
+	TEST_RES(sendto(sk_client, "unreachable", 11, 0,
+			(struct sockaddr *)&sk_addr, sizeof(sk_addr)),
+		 _ret == 11);
+
+	// The queued errors are discarded if `IP_RECVERR` is disabled
+	TEST_SUCC(setsockopt(sk_client, SOL_IP, IP_RECVERR, &val, sizeof(val)));
+	TEST_ERRNO(recvmsg(sk_client, &msg, MSG_ERRQUEUE), EAGAIN);
+	TEST_SUCC(getsockopt(sk_client, SOL_SOCKET, SO_ERROR, &err, &len));
+
+	// No errors are queued if `IP_RECVERR` is disabled
+	TEST_RES(sendto(sk_client, "unreachable", 11, 0,
+			(struct sockaddr *)&sk_addr, sizeof(sk_addr)),
+		 _ret == 11);
+	TEST_ERRNO(recvmsg(sk_client, &msg, MSG_ERRQUEUE), EAGAIN);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_server));
+	CHECK(close(sk_client));
+}
+END_SETUP()
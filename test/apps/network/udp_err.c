@@ -402,3 +402,35 @@ FN_TEST(self_connect)
 	TEST_SUCC(close(sk));
 }
 END_TEST()
+
+FN_TEST(port_unreachable)
+{
+	int sk;
+	int err;
+	socklen_t errlen = sizeof(err);
+	struct pollfd pfd = { .events = POLLIN | POLLOUT };
+	char buf[5];
+
+	sk = TEST_SUCC(socket(PF_INET, SOCK_DGRAM | SOCK_NONBLOCK, 0));
+
+	sk_addr.sin_port = htons(9999);
+	TEST_SUCC(connect(sk, (struct sockaddr *)&sk_addr, sizeof(sk_addr)));
+
+	// No socket is bound to the port, so the error is reported asynchronously
+	TEST_RES(send(sk, "hello", 5, 0), _ret == 5);
+	pfd.fd = sk;
+	TEST_RES(poll(&pfd, 1, 1000),
+		 (pfd.revents & (POLLIN | POLLOUT | POLLERR)) ==
+			 (POLLOUT | POLLERR));
+	TEST_ERRNO(recv(sk, buf, sizeof(buf), 0), ECONNREFUSED);
+	TEST_ERRNO(recv(sk, buf, sizeof(buf), 0), EAGAIN);
+
+	TEST_RES(send(sk, "hello", 5, 0), _ret == 5);
+	TEST_RES(getsockopt(sk, SOL_SOCKET, SO_ERROR, &err, &errlen),
+		 errlen == sizeof(err) && err == ECONNREFUSED);
+	TEST_RES(getsockopt(sk, SOL_SOCKET, SO_ERROR, &err, &errlen),
+		 errlen == sizeof(err) && err == 0);
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <sys/socket.h>
+#include <sys/un.h>
+#include <sys/poll.h>
+#include <fcntl.h>
+#include <string.h>
+#include <unistd.h>
+#include <stddef.h>
+
+#include "test.h"
+
+#define PATH_OFFSET offsetof(struct sockaddr_un, sun_path)
+
+#define SERVER_PATH "/tmp/dgram_server"
+#define CLIENT_PATH "/tmp/dgram_client"
+
+static int sk_server;
+static int sk_client;
+static struct sockaddr_un server_addr = { .sun_family = AF_UNIX,
+					  .sun_path = SERVER_PATH };
+static struct sockaddr_un client_addr = { .sun_family = AF_UNIX,
+					  .sun_path = CLIENT_PATH };
+
+FN_SETUP(bind)
+{
+	sk_server = CHECK(socket(PF_UNIX, SOCK_DGRAM | SOCK_NONBLOCK, 0));
+	sk_client = CHECK(socket(PF_UNIX, SOCK_DGRAM | SOCK_NONBLOCK, 0));
+
+	CHECK(bind(sk_server, (struct sockaddr *)&server_addr,
+		   sizeof(server_addr)));
+	CHECK(bind(sk_client, (struct sockaddr *)&client_addr,
+		   sizeof(client_addr)));
+}
+END_SETUP()
+
+FN_TEST(send_without_address)
+{
+	TEST_ERRNO(send(sk_server, "a", 1, 0), ENOTCONN);
+}
+END_TEST()
+
+FN_TEST(message_boundaries)
+{
+	char buf[16];
+	struct sockaddr_un addr;
+	socklen_t addrlen;
+
+	TEST_RES(sendto(sk_client, "hello", 5, 0,
+			(struct sockaddr *)&server_addr, sizeof(server_addr)),
+		 _ret == 5);
+	TEST_RES(sendto(sk_client, "world!", 6, 0,
+			(struct sockaddr *)&server_addr, sizeof(server_addr)),
+		 _ret == 6);
+
+	addrlen = sizeof(addr);
+	TEST_RES(recvfrom(sk_server, buf, sizeof(buf), 0,
+			  (struct sockaddr *)&addr, &addrlen),
+		 _ret == 5 && memcmp(buf, "hello", 5) == 0 &&
+			 addrlen == PATH_OFFSET + sizeof(CLIENT_PATH) &&
+			 strcmp(addr.sun_path, CLIENT_PATH) == 0);
+
+	// The rest of a message is discarded if the buffer is too small.
+	TEST_RES(recv(sk_server, buf, 3, 0),
+		 _ret == 3 && memcmp(buf, "wor", 3) == 0);
+	TEST_ERRNO(recv(sk_server, buf, sizeof(buf), 0), EAGAIN);
+}
+END_TEST()
+
+FN_TEST(connect)
+{
+	char buf[16];
+	struct sockaddr_un addr;
+	socklen_t addrlen;
+
+	TEST_SUCC(connect(sk_client, (struct sockaddr *)&server_addr,
+			  sizeof(server_addr)));
+
+	addrlen = sizeof(addr);
+	TEST_RES(getpeername(sk_client, (struct sockaddr *)&addr, &addrlen),
+		 addrlen == PATH_OFFSET + sizeof(SERVER_PATH) &&
+			 strcmp(addr.sun_path, SERVER_PATH) == 0);
+
+	TEST_RES(send(sk_client, "abc", 3, 0), _ret == 3);
+	TEST_RES(read(sk_server, buf, sizeof(buf)),
+		 _ret == 3 && memcmp(buf, "abc", 3) == 0);
+}
+END_TEST()
+
+FN_TEST(poll)
+{
+	struct pollfd pfd = { .fd = sk_server, .events = POLLIN | POLLOUT };
+	char buf[16];
+
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && pfd.revents == POLLOUT);
+
+	TEST_RES(send(sk_client, "x", 1, 0), _ret == 1);
+	TEST_RES(poll(&pfd, 1, 0),
+		 _ret == 1 && pfd.revents == (POLLIN | POLLOUT));
+
+	TEST_RES(recv(sk_server, buf, sizeof(buf), 0), _ret == 1);
+}
+END_TEST()
+
+FN_TEST(connect_to_nonexistent)
+{
+	struct sockaddr_un addr = { .sun_family = AF_UNIX,
+				    .sun_path = "/tmp/dgram_nonexistent" };
+	int sk;
+
+	sk = TEST_SUCC(socket(PF_UNIX, SOCK_DGRAM, 0));
+	TEST_ERRNO(connect(sk, (struct sockaddr *)&addr, sizeof(addr)),
+		   ENOENT);
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(socketpair)
+{
+	int fildes[2];
+	char buf[16];
+
+	TEST_SUCC(socketpair(PF_UNIX, SOCK_DGRAM, 0, fildes));
+
+	TEST_RES(write(fildes[0], "ping", 4), _ret == 4);
+	TEST_RES(write(fildes[0], "", 0), _ret == 0);
+	TEST_RES(read(fildes[1], buf, sizeof(buf)),
+		 _ret == 4 && memcmp(buf, "ping", 4) == 0);
+	// An empty datagram is still a datagram.
+	TEST_RES(read(fildes[1], buf, sizeof(buf)), _ret == 0);
+
+	TEST_SUCC(close(fildes[1]));
+	TEST_ERRNO(write(fildes[0], "ping", 4), ECONNREFUSED);
+	TEST_SUCC(close(fildes[0]));
+}
+END_TEST()
+
+FN_TEST(shutdown)
+{
+	int fildes[2];
+	char buf[16];
+
+	TEST_SUCC(socketpair(PF_UNIX, SOCK_DGRAM | SOCK_NONBLOCK, 0, fildes));
+
+	TEST_SUCC(shutdown(fildes[1], SHUT_RD));
+	TEST_RES(recv(fildes[1], buf, sizeof(buf), 0), _ret == 0);
+	TEST_ERRNO(send(fildes[0], "a", 1, MSG_NOSIGNAL), EPIPE);
+
+	TEST_SUCC(shutdown(fildes[1], SHUT_WR));
+	TEST_ERRNO(send(fildes[1], "a", 1, MSG_NOSIGNAL), EPIPE);
+
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_server));
+	CHECK(close(sk_client));
+	CHECK(unlink(SERVER_PATH));
+	CHECK(unlink(CLIENT_PATH));
+}
+END_SETUP()
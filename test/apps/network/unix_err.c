@@ -8,6 +8,7 @@
 #include <sys/epoll.h>
 #include <sys/wait.h>
 #include <fcntl.h>
+#include <signal.h>
 #include <unistd.h>
 #include <stddef.h>
 
@@ -480,6 +481,32 @@ FN_TEST(shutdown_connected)
 }
 END_TEST()
 
+static volatile sig_atomic_t sigpipe_count;
+
+static void count_sigpipe(int signum)
+{
+	++sigpipe_count;
+}
+
+FN_TEST(send_sigpipe)
+{
+	int fildes[2];
+	char buf[1] = { 'z' };
+
+	signal(SIGPIPE, count_sigpipe);
+	TEST_SUCC(socketpair(PF_UNIX, SOCK_STREAM, 0, fildes));
+	TEST_SUCC(shutdown(fildes[0], SHUT_WR));
+
+	TEST(send(fildes[0], buf, 1, MSG_NOSIGNAL), EPIPE, sigpipe_count == 0);
+	TEST(send(fildes[0], buf, 1, 0), EPIPE, sigpipe_count == 1);
+	TEST(write(fildes[0], buf, 1), EPIPE, sigpipe_count == 2);
+
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+	signal(SIGPIPE, SIG_DFL);
+}
+END_TEST()
+
 FN_TEST(poll_unbound)
 {
 	int sk;
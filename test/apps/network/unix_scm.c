@@ -0,0 +1,245 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <sys/socket.h>
+#include <sys/un.h>
+#include <fcntl.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "test.h"
+
+static int sk_pair[2];
+static int pipe_fds[2];
+
+FN_SETUP(socketpair)
+{
+	CHECK(socketpair(PF_UNIX, SOCK_STREAM, 0, sk_pair));
+	CHECK(pipe(pipe_fds));
+}
+END_SETUP()
+
+static int send_fds(int sk, const int *fds, int nr_fds)
+{
+	char data = 'x';
+	struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+	char cbuf[CMSG_SPACE(sizeof(int) * 4)];
+	struct msghdr msg = {
+		.msg_iov = &iov,
+		.msg_iovlen = 1,
+		.msg_control = cbuf,
+		.msg_controllen = CMSG_SPACE(sizeof(int) * nr_fds),
+	};
+	struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+
+	cmsg->cmsg_level = SOL_SOCKET;
+	cmsg->cmsg_type = SCM_RIGHTS;
+	cmsg->cmsg_len = CMSG_LEN(sizeof(int) * nr_fds);
+	memcpy(CMSG_DATA(cmsg), fds, sizeof(int) * nr_fds);
+
+	return sendmsg(sk, &msg, 0);
+}
+
+FN_TEST(scm_rights)
+{
+	char data;
+	struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+	char cbuf[CMSG_SPACE(sizeof(int))];
+	struct msghdr msg = {
+		.msg_iov = &iov,
+		.msg_iovlen = 1,
+		.msg_control = cbuf,
+		.msg_controllen = sizeof(cbuf),
+	};
+	struct cmsghdr *cmsg;
+	int fd;
+	char buf[8];
+
+	TEST_RES(send_fds(sk_pair[0], &pipe_fds[1], 1), _ret == 1);
+
+	TEST_RES(recvmsg(sk_pair[1], &msg, MSG_CMSG_CLOEXEC),
+		 _ret == 1 && data == 'x' && msg.msg_flags == 0 &&
+			 msg.msg_controllen == CMSG_SPACE(sizeof(int)));
+
+	cmsg = CMSG_FIRSTHDR(&msg);
+	TEST_RES(0, cmsg != NULL && cmsg->cmsg_level == SOL_SOCKET &&
+			    cmsg->cmsg_type == SCM_RIGHTS &&
+			    cmsg->cmsg_len == CMSG_LEN(sizeof(int)));
+	memcpy(&fd, CMSG_DATA(cmsg), sizeof(int));
+
+	TEST_RES(fcntl(fd, F_GETFD), _ret == FD_CLOEXEC);
+
+	// The received file refers to the write end of the pipe.
+	TEST_RES(write(fd, "pipe", 4), _ret == 4);
+	TEST_RES(read(pipe_fds[0], buf, sizeof(buf)),
+		 _ret == 4 && memcmp(buf, "pipe", 4) == 0);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(scm_rights_ctrunc)
+{
+	char data;
+	struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+	char cbuf[CMSG_SPACE(sizeof(int))];
+	struct msghdr msg = {
+		.msg_iov = &iov,
+		.msg_iovlen = 1,
+		.msg_control = cbuf,
+		.msg_controllen = sizeof(cbuf),
+	};
+	int fds[2] = { pipe_fds[0], pipe_fds[1] };
+	int fd;
+
+	TEST_RES(send_fds(sk_pair[0], fds, 2), _ret == 1);
+
+	// Only one of the two files fits into the control buffer.
+	TEST_RES(recvmsg(sk_pair[1], &msg, 0),
+		 _ret == 1 && msg.msg_flags == MSG_CTRUNC &&
+			 msg.msg_controllen == CMSG_SPACE(sizeof(int)));
+
+	memcpy(&fd, CMSG_DATA(CMSG_FIRSTHDR(&msg)), sizeof(int));
+	TEST_RES(fcntl(fd, F_GETFD), _ret == 0);
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(scm_rights_no_control_buffer)
+{
+	char data;
+
+	TEST_RES(send_fds(sk_pair[0], &pipe_fds[0], 1), _ret == 1);
+	TEST_RES(recv(sk_pair[1], &data, 1, 0), _ret == 1 && data == 'x');
+}
+END_TEST()
+
+FN_TEST(scm_rights_bad_fd)
+{
+	int fd = 1000;
+
+	TEST_ERRNO(send_fds(sk_pair[0], &fd, 1), EBADF);
+}
+END_TEST()
+
+static int send_creds(int sk, struct ucred *ucred)
+{
+	char data = 'x';
+	struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+	char cbuf[CMSG_SPACE(sizeof(struct ucred))];
+	struct msghdr msg = {
+		.msg_iov = &iov,
+		.msg_iovlen = 1,
+		.msg_control = cbuf,
+		.msg_controllen = sizeof(cbuf),
+	};
+	struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+
+	cmsg->cmsg_level = SOL_SOCKET;
+	cmsg->cmsg_type = SCM_CREDENTIALS;
+	cmsg->cmsg_len = CMSG_LEN(sizeof(struct ucred));
+	memcpy(CMSG_DATA(cmsg), ucred, sizeof(struct ucred));
+
+	return sendmsg(sk, &msg, 0);
+}
+
+FN_TEST(scm_credentials)
+{
+	char data;
+	struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+	char cbuf[CMSG_SPACE(sizeof(struct ucred))];
+	struct msghdr msg = {
+		.msg_iov = &iov,
+		.msg_iovlen = 1,
+		.msg_control = cbuf,
+		.msg_controllen = sizeof(cbuf),
+	};
+	struct ucred ucred = { .pid = getpid(),
+			       .uid = getuid(),
+			       .gid = getgid() };
+	struct ucred received;
+	int enable = 1;
+
+	TEST_SUCC(setsockopt(sk_pair[1], SOL_SOCKET, SO_PASSCRED, &enable,
+			     sizeof(enable)));
+
+	TEST_RES(send_creds(sk_pair[0], &ucred), _ret == 1);
+	TEST_RES(recvmsg(sk_pair[1], &msg, 0),
+		 _ret == 1 && msg.msg_flags == 0 &&
+			 msg.msg_controllen == sizeof(cbuf));
+	memcpy(&received, CMSG_DATA(CMSG_FIRSTHDR(&msg)), sizeof(received));
+	TEST_RES(0, received.pid == ucred.pid && received.uid == ucred.uid &&
+			    received.gid == ucred.gid);
+
+	// The credentials of the sender are received even if they are not sent explicitly.
+	msg.msg_controllen = sizeof(cbuf);
+	TEST_RES(write(sk_pair[0], "y", 1), _ret == 1);
+	TEST_RES(recvmsg(sk_pair[1], &msg, 0),
+		 _ret == 1 && data == 'y' &&
+			 msg.msg_controllen == sizeof(cbuf));
+	memcpy(&received, CMSG_DATA(CMSG_FIRSTHDR(&msg)), sizeof(received));
+	TEST_RES(0, received.pid == ucred.pid && received.uid == ucred.uid &&
+			    received.gid == ucred.gid);
+}
+END_TEST()
+
+FN_TEST(scm_credentials_bad_len)
+{
+	char data = 'x';
+	struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+	char cbuf[CMSG_SPACE(sizeof(struct ucred))];
+	struct msghdr msg = {
+		.msg_iov = &iov,
+		.msg_iovlen = 1,
+		.msg_control = cbuf,
+		.msg_controllen = sizeof(cbuf),
+	};
+	struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+
+	cmsg->cmsg_level = SOL_SOCKET;
+	cmsg->cmsg_type = SCM_CREDENTIALS;
+	cmsg->cmsg_len = CMSG_LEN(sizeof(struct ucred) - 1);
+	TEST_ERRNO(sendmsg(sk_pair[0], &msg, 0), EINVAL);
+
+	cmsg->cmsg_len = sizeof(cbuf) + 1;
+	TEST_ERRNO(sendmsg(sk_pair[0], &msg, 0), EINVAL);
+}
+END_TEST()
+
+FN_TEST(scm_dgram)
+{
+	int fildes[2];
+	char data;
+	struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+	char cbuf[CMSG_SPACE(sizeof(int))];
+	struct msghdr msg = {
+		.msg_iov = &iov,
+		.msg_iovlen = 1,
+		.msg_control = cbuf,
+		.msg_controllen = sizeof(cbuf),
+	};
+	int fd;
+
+	TEST_SUCC(socketpair(PF_UNIX, SOCK_DGRAM, 0, fildes));
+
+	TEST_RES(send_fds(fildes[0], &pipe_fds[0], 1), _ret == 1);
+	TEST_RES(recvmsg(fildes[1], &msg, 0),
+		 _ret == 1 && msg.msg_flags == 0 &&
+			 msg.msg_controllen == CMSG_SPACE(sizeof(int)));
+	memcpy(&fd, CMSG_DATA(CMSG_FIRSTHDR(&msg)), sizeof(int));
+	TEST_SUCC(close(fd));
+
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_pair[0]));
+	CHECK(close(sk_pair[1]));
+	CHECK(close(pipe_fds[0]));
+	CHECK(close(pipe_fds[1]));
+}
+END_SETUP()
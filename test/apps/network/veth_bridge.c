@@ -0,0 +1,375 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <arpa/inet.h>
+#include <linux/if_link.h>
+#include <linux/rtnetlink.h>
+#include <linux/veth.h>
+#include <net/if.h>
+#include <netinet/in.h>
+#include <sched.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "test.h"
+
+#define TEST_PORT 8766
+#define PREFIX_LEN 24
+#define PARENT_ADDR "10.0.7.1"
+#define CHILD_ADDR "10.0.7.2"
+
+struct link_req {
+	struct nlmsghdr hdr;
+	struct ifinfomsg ifi;
+	char attrs[512];
+};
+
+struct link {
+	int index;
+	int master;
+	int peer;
+	char kind[IFNAMSIZ];
+};
+
+static int sk_nl;
+static int to_child[2];
+static int to_parent[2];
+static pid_t child_pid;
+
+static void init_link_req(struct link_req *req, int type, int flags)
+{
+	memset(req, 0, sizeof(*req));
+	req->hdr.nlmsg_len = NLMSG_LENGTH(sizeof(req->ifi));
+	req->hdr.nlmsg_type = type;
+	req->hdr.nlmsg_flags = NLM_F_REQUEST | flags;
+	req->ifi.ifi_family = AF_UNSPEC;
+}
+
+static struct rtattr *add_attr(struct nlmsghdr *hdr, int type, const void *data,
+			       int len)
+{
+	struct rtattr *rta =
+		(struct rtattr *)((char *)hdr + NLMSG_ALIGN(hdr->nlmsg_len));
+
+	rta->rta_type = type;
+	rta->rta_len = RTA_LENGTH(len);
+	if (len > 0)
+		memcpy(RTA_DATA(rta), data, len);
+	hdr->nlmsg_len = NLMSG_ALIGN(hdr->nlmsg_len) + RTA_ALIGN(rta->rta_len);
+
+	return rta;
+}
+
+static void end_nested_attr(struct nlmsghdr *hdr, struct rtattr *nested)
+{
+	nested->rta_len = (char *)hdr + hdr->nlmsg_len - (char *)nested;
+}
+
+static void add_name_attr(struct nlmsghdr *hdr, const char *name)
+{
+	add_attr(hdr, IFLA_IFNAME, name, strlen(name) + 1);
+}
+
+// Sends the request and returns the error code in the acknowledgment.
+static int send_req(int sk, struct nlmsghdr *hdr)
+{
+	char buf[4096];
+	struct nlmsghdr *resp = (struct nlmsghdr *)buf;
+
+	hdr->nlmsg_flags |= NLM_F_ACK;
+	if (send(sk, hdr, hdr->nlmsg_len, 0) != hdr->nlmsg_len)
+		return -1;
+	if (recv(sk, buf, sizeof(buf), 0) < 0 || resp->nlmsg_type != NLMSG_ERROR)
+		return -1;
+	return ((struct nlmsgerr *)NLMSG_DATA(resp))->error;
+}
+
+static int new_veth(int sk, const char *name, const char *peer_name)
+{
+	struct link_req req;
+	struct rtattr *link_info, *data, *peer;
+	struct ifinfomsg peer_ifi;
+
+	init_link_req(&req, RTM_NEWLINK, NLM_F_CREATE | NLM_F_EXCL);
+	add_name_attr(&req.hdr, name);
+
+	link_info = add_attr(&req.hdr, IFLA_LINKINFO, NULL, 0);
+	add_attr(&req.hdr, IFLA_INFO_KIND, "veth", sizeof("veth"));
+	data = add_attr(&req.hdr, IFLA_INFO_DATA, NULL, 0);
+	memset(&peer_ifi, 0, sizeof(peer_ifi));
+	peer = add_attr(&req.hdr, VETH_INFO_PEER, &peer_ifi, sizeof(peer_ifi));
+	add_name_attr(&req.hdr, peer_name);
+	end_nested_attr(&req.hdr, peer);
+	end_nested_attr(&req.hdr, data);
+	end_nested_attr(&req.hdr, link_info);
+
+	return send_req(sk, &req.hdr);
+}
+
+static int new_bridge(int sk, const char *name)
+{
+	struct link_req req;
+	struct rtattr *link_info;
+
+	init_link_req(&req, RTM_NEWLINK, NLM_F_CREATE | NLM_F_EXCL);
+	add_name_attr(&req.hdr, name);
+
+	link_info = add_attr(&req.hdr, IFLA_LINKINFO, NULL, 0);
+	add_attr(&req.hdr, IFLA_INFO_KIND, "bridge", sizeof("bridge"));
+	end_nested_attr(&req.hdr, link_info);
+
+	return send_req(sk, &req.hdr);
+}
+
+// Sets a 32-bit attribute of the link, e.g., its master or its network namespace.
+static int set_link_attr(int sk, const char *name, int type, int value)
+{
+	struct link_req req;
+
+	init_link_req(&req, RTM_NEWLINK, 0);
+	add_name_attr(&req.hdr, name);
+	add_attr(&req.hdr, type, &value, sizeof(value));
+
+	return send_req(sk, &req.hdr);
+}
+
+static int del_link(int sk, const char *name)
+{
+	struct link_req req;
+
+	init_link_req(&req, RTM_DELLINK, 0);
+	add_name_attr(&req.hdr, name);
+
+	return send_req(sk, &req.hdr);
+}
+
+static int get_link(int sk, const char *name, struct link *link)
+{
+	struct link_req req;
+	char buf[4096];
+	struct nlmsghdr *hdr = (struct nlmsghdr *)buf;
+	struct ifinfomsg *ifi = NLMSG_DATA(hdr);
+	struct rtattr *rta, *nested;
+	int len, nested_len;
+
+	init_link_req(&req, RTM_GETLINK, 0);
+	add_name_attr(&req.hdr, name);
+
+	if (send(sk, &req, req.hdr.nlmsg_len, 0) != req.hdr.nlmsg_len)
+		return -1;
+	if (recv(sk, buf, sizeof(buf), 0) < 0)
+		return -1;
+	if (hdr->nlmsg_type == NLMSG_ERROR)
+		return ((struct nlmsgerr *)NLMSG_DATA(hdr))->error;
+	if (hdr->nlmsg_type != RTM_NEWLINK)
+		return -1;
+
+	memset(link, 0, sizeof(*link));
+	link->index = ifi->ifi_index;
+
+	len = IFLA_PAYLOAD(hdr);
+	for (rta = IFLA_RTA(ifi); RTA_OK(rta, len); rta = RTA_NEXT(rta, len)) {
+		switch (rta->rta_type) {
+		case IFLA_MASTER:
+			link->master = *(int *)RTA_DATA(rta);
+			break;
+		case IFLA_LINK:
+			link->peer = *(int *)RTA_DATA(rta);
+			break;
+		case IFLA_LINKINFO:
+			nested_len = RTA_PAYLOAD(rta);
+			for (nested = RTA_DATA(rta); RTA_OK(nested, nested_len);
+			     nested = RTA_NEXT(nested, nested_len))
+				if (nested->rta_type == IFLA_INFO_KIND)
+					strncpy(link->kind, RTA_DATA(nested),
+						sizeof(link->kind) - 1);
+			break;
+		}
+	}
+
+	return 0;
+}
+
+static int add_addr(int sk, int index, const char *addr)
+{
+	struct {
+		struct nlmsghdr hdr;
+		struct ifaddrmsg ifa;
+		struct rtattr local_attr;
+		struct in_addr local;
+	} req;
+
+	memset(&req, 0, sizeof(req));
+	req.hdr.nlmsg_len = sizeof(req);
+	req.hdr.nlmsg_type = RTM_NEWADDR;
+	req.hdr.nlmsg_flags = NLM_F_REQUEST | NLM_F_CREATE | NLM_F_EXCL;
+	req.ifa.ifa_family = AF_INET;
+	req.ifa.ifa_prefixlen = PREFIX_LEN;
+	req.ifa.ifa_index = index;
+	req.local_attr.rta_len = RTA_LENGTH(sizeof(req.local));
+	req.local_attr.rta_type = IFA_LOCAL;
+	if (inet_pton(AF_INET, addr, &req.local) != 1)
+		return -1;
+
+	return send_req(sk, &req.hdr);
+}
+
+// Configures the veth device moved to the new network namespace, and serves one TCP
+// connection from the initial network namespace.
+static void run_child(void)
+{
+	struct sockaddr_in addr;
+	struct link link;
+	int sk, sk_listen, sk_accepted;
+	char byte;
+
+	CHECK(unshare(CLONE_NEWNET));
+	CHECK_WITH(write(to_parent[1], "r", 1), _ret == 1);
+	CHECK_WITH(read(to_child[0], &byte, 1), _ret == 1);
+
+	sk = CHECK(socket(AF_NETLINK, SOCK_RAW, NETLINK_ROUTE));
+	CHECK_WITH(get_link(sk, "vb1", &link), _ret == -ENODEV);
+	CHECK_WITH(get_link(sk, "va1", &link),
+		   _ret == 0 && strcmp(link.kind, "veth") == 0 &&
+			   link.peer == 0);
+	CHECK_WITH(add_addr(sk, link.index, CHILD_ADDR), _ret == 0);
+	CHECK(close(sk));
+
+	memset(&addr, 0, sizeof(addr));
+	addr.sin_family = AF_INET;
+	addr.sin_port = htons(TEST_PORT);
+	CHECK_WITH(inet_pton(AF_INET, CHILD_ADDR, &addr.sin_addr), _ret == 1);
+
+	sk_listen = CHECK(socket(AF_INET, SOCK_STREAM, 0));
+	CHECK(bind(sk_listen, (struct sockaddr *)&addr, sizeof(addr)));
+	CHECK(listen(sk_listen, 1));
+	CHECK_WITH(write(to_parent[1], "l", 1), _ret == 1);
+
+	sk_accepted = CHECK(accept(sk_listen, NULL, NULL));
+	CHECK_WITH(recv(sk_accepted, &byte, 1, 0), _ret == 1 && byte == 'x');
+	CHECK_WITH(send(sk_accepted, "y", 1, 0), _ret == 1);
+
+	CHECK(close(sk_accepted));
+	CHECK(close(sk_listen));
+}
+
+FN_SETUP(init)
+{
+	char byte;
+
+	sk_nl = CHECK(socket(AF_NETLINK, SOCK_RAW, NETLINK_ROUTE));
+
+	CHECK(pipe(to_child));
+	CHECK(pipe(to_parent));
+
+	child_pid = CHECK(fork());
+	if (child_pid == 0) {
+		run_child();
+		exit(EXIT_SUCCESS);
+	}
+
+	// Wait until the child process is in its own network namespace.
+	CHECK_WITH(read(to_parent[0], &byte, 1), _ret == 1);
+}
+END_SETUP()
+
+FN_TEST(veth_pair)
+{
+	struct link a, b;
+
+	TEST_RES(new_veth(sk_nl, "veth_a", "veth_b"), _ret == 0);
+	TEST_RES(new_veth(sk_nl, "veth_a", "veth_c"), _ret == -EEXIST);
+	TEST_RES(new_veth(sk_nl, "veth_c", "veth_b"), _ret == -EEXIST);
+	TEST_RES(get_link(sk_nl, "veth_c", &a), _ret == -ENODEV);
+
+	TEST_RES(get_link(sk_nl, "veth_a", &a),
+		 _ret == 0 && strcmp(a.kind, "veth") == 0);
+	TEST_RES(get_link(sk_nl, "veth_b", &b),
+		 _ret == 0 && strcmp(b.kind, "veth") == 0 &&
+			 b.peer == a.index && a.peer == b.index);
+
+	// Deleting one end deletes the pair.
+	TEST_RES(del_link(sk_nl, "veth_b"), _ret == 0);
+	TEST_RES(get_link(sk_nl, "veth_a", &a), _ret == -ENODEV);
+	TEST_RES(get_link(sk_nl, "veth_b", &b), _ret == -ENODEV);
+
+	// Physical links cannot be deleted.
+	TEST_RES(get_link(sk_nl, "lo", &a), _ret == 0 && a.kind[0] == '\0');
+	TEST_RES(del_link(sk_nl, "lo"), _ret == -EOPNOTSUPP);
+}
+END_TEST()
+
+FN_TEST(bridge_across_ns)
+{
+	struct sockaddr_in addr;
+	struct link br, va0, vb0, vb1;
+	int sk, status;
+	char byte;
+
+	TEST_RES(new_bridge(sk_nl, "br_test"), _ret == 0);
+	TEST_RES(new_veth(sk_nl, "va0", "vb0"), _ret == 0);
+	TEST_RES(new_veth(sk_nl, "va1", "vb1"), _ret == 0);
+	TEST_RES(get_link(sk_nl, "br_test", &br),
+		 _ret == 0 && strcmp(br.kind, "bridge") == 0);
+
+	// Move one end to the network namespace of the child process.
+	TEST_RES(set_link_attr(sk_nl, "va1", IFLA_NET_NS_PID, child_pid),
+		 _ret == 0);
+	TEST_RES(get_link(sk_nl, "va1", &va0), _ret == -ENODEV);
+	TEST_RES(set_link_attr(sk_nl, "br_test", IFLA_NET_NS_PID, child_pid),
+		 _ret == -EINVAL);
+	TEST_RES(set_link_attr(sk_nl, "lo", IFLA_NET_NS_PID, child_pid),
+		 _ret == -EINVAL);
+
+	// Attach the other ends to the bridge.
+	TEST_RES(set_link_attr(sk_nl, "vb0", IFLA_MASTER, br.index), _ret == 0);
+	TEST_RES(set_link_attr(sk_nl, "vb1", IFLA_MASTER, br.index), _ret == 0);
+	TEST_RES(set_link_attr(sk_nl, "br_test", IFLA_MASTER, br.index),
+		 _ret == -ELOOP);
+	TEST_RES(get_link(sk_nl, "vb0", &vb0),
+		 _ret == 0 && vb0.master == br.index);
+	TEST_RES(get_link(sk_nl, "vb1", &vb1),
+		 _ret == 0 && vb1.master == br.index && vb1.peer == 0);
+
+	TEST_RES(get_link(sk_nl, "va0", &va0),
+		 _ret == 0 && va0.peer == vb0.index && va0.master == 0);
+	TEST_RES(add_addr(sk_nl, va0.index, PARENT_ADDR), _ret == 0);
+
+	// Connect to the child process through the bridge.
+	TEST_RES(write(to_child[1], "c", 1), _ret == 1);
+	TEST_RES(read(to_parent[0], &byte, 1), _ret == 1 && byte == 'l');
+
+	memset(&addr, 0, sizeof(addr));
+	addr.sin_family = AF_INET;
+	addr.sin_port = htons(TEST_PORT);
+	TEST_RES(inet_pton(AF_INET, CHILD_ADDR, &addr.sin_addr), _ret == 1);
+
+	sk = TEST_SUCC(socket(AF_INET, SOCK_STREAM, 0));
+	TEST_SUCC(connect(sk, (struct sockaddr *)&addr, sizeof(addr)));
+	TEST_RES(send(sk, "x", 1, 0), _ret == 1);
+	TEST_RES(recv(sk, &byte, 1, 0), _ret == 1 && byte == 'y');
+	TEST_SUCC(close(sk));
+
+	TEST_RES(waitpid(child_pid, &status, 0),
+		 _ret == child_pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == 0);
+
+	// Deleting the bridge detaches its ports.
+	TEST_RES(del_link(sk_nl, "br_test"), _ret == 0);
+	TEST_RES(get_link(sk_nl, "vb0", &vb0), _ret == 0 && vb0.master == 0);
+
+	TEST_RES(del_link(sk_nl, "va0"), _ret == 0);
+	TEST_RES(del_link(sk_nl, "vb1"), _ret == 0 || _ret == -ENODEV);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(to_child[0]));
+	CHECK(close(to_child[1]));
+	CHECK(close(to_parent[0]));
+	CHECK(close(to_parent[1]));
+	CHECK(close(sk_nl));
+}
+END_SETUP()
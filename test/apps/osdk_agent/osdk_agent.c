@@ -0,0 +1,356 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// The guest agent of OSDK, which runs as the init process and executes the
+// requests of OSDK received from the console one by one.
+//
+// See `osdk/src/commands/agent.rs` for the protocol.
+
+#define _GNU_SOURCE
+
+#include <errno.h>
+#include <fcntl.h>
+#include <poll.h>
+#include <stdarg.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <termios.h>
+#include <unistd.h>
+
+#define MAGIC "@osdk-agent"
+#define MAX_LINE (64 * 1024)
+#define MAX_ARGS 256
+#define CHUNK_SIZE 1024
+
+static char line[MAX_LINE];
+
+static void write_all(const char *buf, size_t len)
+{
+	while (len > 0) {
+		ssize_t n = write(STDOUT_FILENO, buf, len);
+		if (n < 0 && errno == EINTR)
+			continue;
+		if (n <= 0)
+			exit(EXIT_FAILURE);
+		buf += n;
+		len -= n;
+	}
+}
+
+// Each message is written with a single `write`, so that it is not mixed with
+// the output of the kernel.
+static void send_message(const char *fmt, ...)
+{
+	static char msg[128];
+	va_list args;
+	int len;
+
+	va_start(args, fmt);
+	len = vsnprintf(msg, sizeof(msg) - 1, fmt, args);
+	va_end(args);
+	msg[len++] = '\n';
+	write_all(msg, len);
+}
+
+static void send_data(const char *kind, unsigned long id, const char *data,
+		      size_t len)
+{
+	static char msg[sizeof(MAGIC) + 32 + CHUNK_SIZE * 2];
+	size_t chunk, pos, i;
+
+	for (; len > 0; data += chunk, len -= chunk) {
+		chunk = len < CHUNK_SIZE ? len : CHUNK_SIZE;
+		pos = sprintf(msg, MAGIC " %s %lu ", kind, id);
+		for (i = 0; i < chunk; i++)
+			pos += sprintf(msg + pos, "%02x", (unsigned char)data[i]);
+		msg[pos++] = '\n';
+		write_all(msg, pos);
+	}
+}
+
+static void send_error(unsigned long id, const char *what, const char *path)
+{
+	char err[512];
+	int len;
+
+	len = snprintf(err, sizeof(err), "%s %s: %s\n", what, path,
+		       strerror(errno));
+	send_data("ERR", id, err, len);
+	send_message(MAGIC " EXIT %lu 1", id);
+}
+
+// Decodes the hex string in place and returns the length of the data, or -1
+// if the string is invalid.
+static ssize_t decode_hex(char *text)
+{
+	size_t len = strlen(text), i;
+	unsigned int byte;
+
+	if (len % 2 != 0)
+		return -1;
+	for (i = 0; i < len / 2; i++) {
+		if (sscanf(text + i * 2, "%2x", &byte) != 1)
+			return -1;
+		text[i] = byte;
+	}
+	text[i] = '\0';
+	return i;
+}
+
+static void forward_output(int fd, const char *kind, unsigned long id,
+			   struct pollfd *pfd)
+{
+	char buf[CHUNK_SIZE];
+	ssize_t n = read(fd, buf, sizeof(buf));
+
+	if (n > 0)
+		send_data(kind, id, buf, n);
+	else if (n == 0 || errno != EINTR)
+		pfd->fd = -1;
+}
+
+static void handle_run(unsigned long id, char **fields, int nr_fields)
+{
+	char *argv[MAX_ARGS + 1];
+	int out_pipe[2], err_pipe[2];
+	struct pollfd pfds[2];
+	pid_t pid;
+	int i, status, code;
+
+	if (nr_fields == 0 || nr_fields > MAX_ARGS)
+		goto invalid;
+	for (i = 0; i < nr_fields; i++) {
+		if (decode_hex(fields[i]) < 0)
+			goto invalid;
+		argv[i] = fields[i];
+	}
+	argv[nr_fields] = NULL;
+
+	if (pipe(out_pipe) < 0) {
+		send_error(id, "cannot create pipes for", argv[0]);
+		return;
+	}
+	if (pipe(err_pipe) < 0) {
+		send_error(id, "cannot create pipes for", argv[0]);
+		goto close_out_pipe;
+	}
+
+	pid = fork();
+	if (pid < 0) {
+		send_error(id, "cannot fork for", argv[0]);
+		close(err_pipe[0]);
+		close(err_pipe[1]);
+		goto close_out_pipe;
+	}
+	if (pid == 0) {
+		int null_fd = open("/dev/null", O_RDONLY);
+
+		dup2(null_fd, STDIN_FILENO);
+		dup2(out_pipe[1], STDOUT_FILENO);
+		dup2(err_pipe[1], STDERR_FILENO);
+		close(null_fd);
+		close(out_pipe[0]);
+		close(out_pipe[1]);
+		close(err_pipe[0]);
+		close(err_pipe[1]);
+		execvp(argv[0], argv);
+		fprintf(stderr, "cannot execute %s: %s\n", argv[0],
+			strerror(errno));
+		_exit(127);
+	}
+
+	close(out_pipe[1]);
+	close(err_pipe[1]);
+	pfds[0].fd = out_pipe[0];
+	pfds[1].fd = err_pipe[0];
+	pfds[0].events = pfds[1].events = POLLIN;
+	while (pfds[0].fd >= 0 || pfds[1].fd >= 0) {
+		if (poll(pfds, 2, -1) < 0) {
+			if (errno == EINTR)
+				continue;
+			break;
+		}
+		if (pfds[0].revents)
+			forward_output(out_pipe[0], "OUT", id, &pfds[0]);
+		if (pfds[1].revents)
+			forward_output(err_pipe[0], "ERR", id, &pfds[1]);
+	}
+	close(out_pipe[0]);
+	close(err_pipe[0]);
+
+	while (waitpid(pid, &status, 0) < 0 && errno == EINTR)
+		;
+	if (WIFEXITED(status))
+		code = WEXITSTATUS(status);
+	else
+		code = 128 + WTERMSIG(status);
+	send_message(MAGIC " EXIT %lu %d", id, code);
+
+	// As the init process, the agent also reaps the orphaned processes.
+	while (waitpid(-1, NULL, WNOHANG) > 0)
+		;
+	return;
+
+close_out_pipe:
+	close(out_pipe[0]);
+	close(out_pipe[1]);
+	return;
+
+invalid:
+	send_message(MAGIC " EXIT %lu 127", id);
+}
+
+static void handle_put(unsigned long id, char **fields, int nr_fields)
+{
+	char *path, *data = "";
+	unsigned long mode;
+	long offset;
+	ssize_t len;
+	int fd, flags = O_WRONLY | O_CREAT;
+
+	// The data field is omitted if it is empty.
+	if (nr_fields != 3 && nr_fields != 4)
+		goto invalid;
+	path = fields[0];
+	if (decode_hex(path) < 0)
+		goto invalid;
+	mode = strtoul(fields[1], NULL, 8);
+	offset = atol(fields[2]);
+	len = 0;
+	if (nr_fields == 4) {
+		data = fields[3];
+		len = decode_hex(data);
+		if (len < 0)
+			goto invalid;
+	}
+
+	if (offset == 0)
+		flags |= O_TRUNC;
+	fd = open(path, flags, mode);
+	if (fd < 0) {
+		send_error(id, "cannot open", path);
+		return;
+	}
+	if ((offset == 0 && fchmod(fd, mode) < 0) ||
+	    pwrite(fd, data, len, offset) != len) {
+		send_error(id, "cannot write", path);
+		close(fd);
+		return;
+	}
+	close(fd);
+	send_message(MAGIC " EXIT %lu 0", id);
+	return;
+
+invalid:
+	send_message(MAGIC " EXIT %lu 1", id);
+}
+
+static void handle_get(unsigned long id, char **fields, int nr_fields)
+{
+	char buf[CHUNK_SIZE];
+	ssize_t n;
+	int fd;
+
+	if (nr_fields != 1 || decode_hex(fields[0]) < 0) {
+		send_message(MAGIC " EXIT %lu 1", id);
+		return;
+	}
+
+	fd = open(fields[0], O_RDONLY);
+	if (fd < 0) {
+		send_error(id, "cannot open", fields[0]);
+		return;
+	}
+	while ((n = read(fd, buf, sizeof(buf))) > 0)
+		send_data("OUT", id, buf, n);
+	if (n < 0) {
+		send_error(id, "cannot read", fields[0]);
+		close(fd);
+		return;
+	}
+	close(fd);
+	send_message(MAGIC " EXIT %lu 0", id);
+}
+
+// Reads a line from the console into `line` without the trailing newline.
+//
+// Returns the length of the line, or -1 at the end of the input.
+static ssize_t read_line(void)
+{
+	static char buf[4096];
+	static size_t start, end;
+	size_t len = 0;
+	ssize_t n;
+
+	for (;;) {
+		while (start < end) {
+			char c = buf[start++];
+
+			if (c == '\n' || c == '\r') {
+				if (len == 0)
+					continue;
+				line[len] = '\0';
+				return len;
+			}
+			// Overlong lines are truncated, which makes them invalid.
+			if (len < MAX_LINE - 1)
+				line[len++] = c;
+		}
+
+		n = read(STDIN_FILENO, buf, sizeof(buf));
+		if (n < 0 && errno == EINTR)
+			continue;
+		if (n <= 0)
+			return -1;
+		start = 0;
+		end = n;
+	}
+}
+
+int main(void)
+{
+	struct termios termios;
+	char *fields[MAX_ARGS + 3];
+	int nr_fields;
+	unsigned long id;
+	char *kind, *field;
+
+	// The requests must not be echoed or altered by the terminal.
+	if (tcgetattr(STDIN_FILENO, &termios) == 0) {
+		cfmakeraw(&termios);
+		tcsetattr(STDIN_FILENO, TCSANOW, &termios);
+	}
+
+	send_message(MAGIC " READY");
+
+	while (read_line() >= 0) {
+		if (strtok(line, " ") == NULL || strcmp(line, MAGIC) != 0)
+			continue;
+		kind = strtok(NULL, " ");
+		field = strtok(NULL, " ");
+		if (kind == NULL || field == NULL)
+			continue;
+
+		if (strcmp(kind, "QUIT") == 0)
+			return atoi(field);
+
+		id = strtoul(field, NULL, 10);
+		nr_fields = 0;
+		while (nr_fields < MAX_ARGS + 3 &&
+		       (field = strtok(NULL, " ")) != NULL)
+			fields[nr_fields++] = field;
+
+		if (strcmp(kind, "RUN") == 0)
+			handle_run(id, fields, nr_fields);
+		else if (strcmp(kind, "PUT") == 0)
+			handle_put(id, fields, nr_fields);
+		else if (strcmp(kind, "GET") == 0)
+			handle_get(id, fields, nr_fields);
+		else
+			send_message(MAGIC " EXIT %lu 1", id);
+	}
+
+	return EXIT_SUCCESS;
+}
@@ -0,0 +1,162 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <dirent.h>
+#include <fcntl.h>
+#include <string.h>
+#include <sys/mount.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define OVERLAYDIR "/tmp/ovl_ops"
+#define LOWERDIR OVERLAYDIR "/lower"
+#define UPPERDIR OVERLAYDIR "/upper"
+#define WORKDIR OVERLAYDIR "/work"
+#define MERGEDDIR OVERLAYDIR "/merged"
+
+#define OPTIONS "lowerdir=" LOWERDIR ",upperdir=" UPPERDIR ",workdir=" WORKDIR
+
+static void write_file(const char *path, const char *data)
+{
+	int fd;
+
+	fd = CHECK(open(path, O_WRONLY | O_CREAT | O_TRUNC, 0644));
+	CHECK_WITH(write(fd, data, strlen(data)), _ret == strlen(data));
+	CHECK(close(fd));
+}
+
+static int file_has(const char *path, const char *data)
+{
+	char buf[64];
+	int fd, len;
+
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return 0;
+	len = read(fd, buf, sizeof(buf));
+	close(fd);
+
+	return len == strlen(data) && memcmp(buf, data, len) == 0;
+}
+
+static int dir_has(const char *path, const char *name)
+{
+	struct dirent *entry;
+	DIR *dir;
+	int found = 0;
+
+	dir = opendir(path);
+	if (dir == NULL)
+		return -1;
+	while ((entry = readdir(dir)) != NULL)
+		if (strcmp(entry->d_name, name) == 0)
+			found = 1;
+	closedir(dir);
+
+	return found;
+}
+
+FN_SETUP(overlay)
+{
+	CHECK(mkdir(OVERLAYDIR, 0755));
+	CHECK(mkdir(LOWERDIR, 0755));
+	CHECK(mkdir(UPPERDIR, 0755));
+	CHECK(mkdir(WORKDIR, 0755));
+	CHECK(mkdir(MERGEDDIR, 0755));
+
+	CHECK(mkdir(LOWERDIR "/dir", 0755));
+	write_file(LOWERDIR "/dir/file", "lower file");
+	write_file(LOWERDIR "/file", "lower");
+	write_file(LOWERDIR "/removed", "removed");
+
+	CHECK(mount("overlay", MERGEDDIR, "overlay", 0, OPTIONS));
+}
+END_SETUP()
+
+FN_TEST(invalid_options)
+{
+	TEST_ERRNO(mount("overlay", MERGEDDIR, "overlay", 0,
+			 "upperdir=" UPPERDIR ",workdir=" WORKDIR),
+		   EINVAL);
+	TEST_ERRNO(mount("overlay", MERGEDDIR, "overlay", 0,
+			 "lowerdir=" OVERLAYDIR "/none,upperdir=" UPPERDIR
+			 ",workdir=" WORKDIR),
+		   ENOENT);
+	TEST_ERRNO(mount("overlay", MERGEDDIR, "overlay", 0,
+			 "lowerdir=" LOWERDIR "/file,upperdir=" UPPERDIR
+			 ",workdir=" WORKDIR),
+		   EINVAL);
+}
+END_TEST()
+
+FN_TEST(copy_up)
+{
+	int fd;
+
+	fd = TEST_SUCC(open(MERGEDDIR "/dir/file", O_WRONLY | O_APPEND));
+	TEST_RES(write(fd, " changed", 8), _ret == 8);
+	TEST_SUCC(close(fd));
+
+	TEST_RES(file_has(MERGEDDIR "/dir/file", "lower file changed"),
+		 _ret == 1);
+	TEST_RES(file_has(UPPERDIR "/dir/file", "lower file changed"),
+		 _ret == 1);
+	TEST_RES(file_has(LOWERDIR "/dir/file", "lower file"), _ret == 1);
+}
+END_TEST()
+
+FN_TEST(whiteout)
+{
+	TEST_SUCC(unlink(MERGEDDIR "/removed"));
+	TEST_ERRNO(access(MERGEDDIR "/removed", F_OK), ENOENT);
+	TEST_RES(dir_has(MERGEDDIR, "removed"), _ret == 0);
+	TEST_RES(file_has(LOWERDIR "/removed", "removed"), _ret == 1);
+
+	TEST_SUCC(mkfifo(MERGEDDIR "/removed", 0644));
+	TEST_RES(dir_has(MERGEDDIR, "removed"), _ret == 1);
+	TEST_SUCC(unlink(MERGEDDIR "/removed"));
+}
+END_TEST()
+
+FN_TEST(rename_file)
+{
+	TEST_SUCC(rename(MERGEDDIR "/file", MERGEDDIR "/dir/renamed"));
+	TEST_ERRNO(access(MERGEDDIR "/file", F_OK), ENOENT);
+	TEST_RES(file_has(MERGEDDIR "/dir/renamed", "lower"), _ret == 1);
+	TEST_RES(file_has(LOWERDIR "/file", "lower"), _ret == 1);
+
+	// Rename the file back over its whiteout.
+	TEST_SUCC(rename(MERGEDDIR "/dir/renamed", MERGEDDIR "/file"));
+	TEST_RES(file_has(MERGEDDIR "/file", "lower"), _ret == 1);
+	TEST_RES(dir_has(MERGEDDIR "/dir", "renamed"), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(rename_dir)
+{
+	TEST_SUCC(mkdir(MERGEDDIR "/new_dir", 0755));
+	write_file(MERGEDDIR "/new_dir/file", "new file");
+	TEST_SUCC(rename(MERGEDDIR "/new_dir", MERGEDDIR "/renamed_dir"));
+	TEST_RES(file_has(MERGEDDIR "/renamed_dir/file", "new file"),
+		 _ret == 1);
+	TEST_SUCC(unlink(MERGEDDIR "/renamed_dir/file"));
+	TEST_SUCC(rmdir(MERGEDDIR "/renamed_dir"));
+}
+END_TEST()
+
+FN_TEST(link)
+{
+	TEST_SUCC(link(MERGEDDIR "/file", MERGEDDIR "/dir/link"));
+	TEST_RES(file_has(MERGEDDIR "/dir/link", "lower"), _ret == 1);
+	TEST_SUCC(unlink(MERGEDDIR "/dir/link"));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(umount(MERGEDDIR));
+}
+END_SETUP()
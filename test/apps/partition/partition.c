@@ -0,0 +1,244 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+#include <fcntl.h>
+#include <linux/fs.h>
+#include <stdint.h>
+#include <sys/ioctl.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#define DISK_PATH "/dev/vda"
+
+// The disk is a 64 MiB blank image, see `tools/qemu_args.sh`.
+#define SECTOR_SIZE 512
+#define DISK_SECTORS (64 * 1024 * 1024 / SECTOR_SIZE)
+
+#define GPT_ENTRIES 128
+#define GPT_ENTRY_SIZE 128
+#define GPT_ENTRIES_SECTORS (GPT_ENTRIES * GPT_ENTRY_SIZE / SECTOR_SIZE)
+
+static int disk_fd;
+static unsigned char sector[SECTOR_SIZE];
+static unsigned char gpt_entries[GPT_ENTRIES * GPT_ENTRY_SIZE];
+
+static void put_u32(unsigned char *buf, uint32_t val)
+{
+	for (int i = 0; i < 4; i++)
+		buf[i] = val >> (i * 8);
+}
+
+static void put_u64(unsigned char *buf, uint64_t val)
+{
+	for (int i = 0; i < 8; i++)
+		buf[i] = val >> (i * 8);
+}
+
+static uint32_t crc32(const unsigned char *buf, size_t len)
+{
+	uint32_t crc = ~0u;
+
+	for (size_t i = 0; i < len; i++) {
+		crc ^= buf[i];
+		for (int j = 0; j < 8; j++)
+			crc = (crc >> 1) ^ (0xedb88320 & -(crc & 1));
+	}
+	return ~crc;
+}
+
+// Fills the `index`-th entry of the MBR or an EBR in `sector`.
+static void set_mbr_entry(int index, unsigned char type, uint32_t start,
+			  uint32_t nr_sectors)
+{
+	unsigned char *entry = sector + 446 + index * 16;
+
+	entry[0] = 0;
+	entry[4] = type;
+	put_u32(entry + 8, start);
+	put_u32(entry + 12, nr_sectors);
+	sector[510] = 0x55;
+	sector[511] = 0xaa;
+}
+
+static int write_sector(uint64_t sid)
+{
+	ssize_t len = pwrite(disk_fd, sector, SECTOR_SIZE, sid * SECTOR_SIZE);
+
+	memset(sector, 0, SECTOR_SIZE);
+	return len == SECTOR_SIZE ? 0 : -1;
+}
+
+static uint64_t partition_size(const char *path)
+{
+	uint64_t size = 0;
+	int fd = open(path, O_RDONLY);
+
+	if (fd < 0)
+		return 0;
+	if (ioctl(fd, BLKGETSIZE64, &size) < 0)
+		size = 0;
+	close(fd);
+	return size;
+}
+
+FN_SETUP(open)
+{
+	disk_fd = CHECK(open(DISK_PATH, O_RDWR));
+}
+END_SETUP()
+
+FN_TEST(disk_info)
+{
+	uint64_t size;
+	int sector_size;
+	struct stat stat_buf;
+
+	TEST_RES(ioctl(disk_fd, BLKGETSIZE64, &size),
+		 size == (uint64_t)DISK_SECTORS * SECTOR_SIZE);
+	TEST_RES(ioctl(disk_fd, BLKSSZGET, &sector_size),
+		 sector_size == SECTOR_SIZE);
+	TEST_RES(stat(DISK_PATH, &stat_buf), S_ISBLK(stat_buf.st_mode));
+}
+END_TEST()
+
+// The layout of the MBR:
+// - 1: the primary partition of the sectors [2048, 10240);
+// - 2: the extended partition of the sectors [10240, 30720), which contains
+//   - 5: the logical partition of the sectors [12288, 16384);
+//   - 6: the logical partition of the sectors [20480, 22528);
+// - 3: the primary partition of the sectors [40960, 57344).
+FN_SETUP(write_mbr)
+{
+	set_mbr_entry(0, 0x83, 2048, 8192);
+	set_mbr_entry(1, 0x05, 10240, 20480);
+	set_mbr_entry(2, 0x83, 40960, 16384);
+	CHECK(write_sector(0));
+
+	set_mbr_entry(0, 0x83, 2048, 4096);
+	set_mbr_entry(1, 0x05, 8192, 4096);
+	CHECK(write_sector(10240));
+
+	set_mbr_entry(0, 0x83, 2048, 2048);
+	CHECK(write_sector(18432));
+
+	CHECK(ioctl(disk_fd, BLKRRPART));
+}
+END_SETUP()
+
+FN_TEST(mbr_partitions)
+{
+	TEST_RES(partition_size("/dev/vda1"), _ret == 8192 * SECTOR_SIZE);
+	TEST_RES(partition_size("/dev/vda3"), _ret == 16384 * SECTOR_SIZE);
+	TEST_RES(partition_size("/dev/vda5"), _ret == 4096 * SECTOR_SIZE);
+	TEST_RES(partition_size("/dev/vda6"), _ret == 2048 * SECTOR_SIZE);
+	TEST_ERRNO(access("/dev/vda7", F_OK), ENOENT);
+}
+END_TEST()
+
+FN_TEST(partition_io)
+{
+	char buf[16];
+	int fd;
+
+	fd = TEST_SUCC(open("/dev/vda5", O_RDWR));
+
+	// The unaligned accesses are allowed.
+	TEST_RES(pwrite(fd, "hello", 5, 0), _ret == 5);
+	TEST_RES(pwrite(fd, "world", 5, 1021), _ret == 5);
+	TEST_RES(pread(disk_fd, buf, 5, 12288 * SECTOR_SIZE),
+		 _ret == 5 && memcmp(buf, "hello", 5) == 0);
+	TEST_RES(pread(disk_fd, buf, 5, 12288 * SECTOR_SIZE + 1021),
+		 _ret == 5 && memcmp(buf, "world", 5) == 0);
+	TEST_RES(pread(fd, buf, 7, 0),
+		 _ret == 7 && memcmp(buf, "hello\0\0", 7) == 0);
+
+	// The accesses are truncated at the end of the partition.
+	TEST_RES(pread(fd, buf, sizeof(buf), 4096 * SECTOR_SIZE - 4),
+		 _ret == 4);
+	TEST_RES(pread(fd, buf, sizeof(buf), 4096 * SECTOR_SIZE), _ret == 0);
+	TEST_ERRNO(pwrite(fd, buf, sizeof(buf), 4096 * SECTOR_SIZE), ENOSPC);
+
+	// The partitions cannot have partitions.
+	TEST_ERRNO(ioctl(fd, BLKRRPART), EINVAL);
+
+	// The partitions cannot be removed while they are opened.
+	TEST_ERRNO(ioctl(disk_fd, BLKRRPART), EBUSY);
+	TEST_SUCC(close(fd));
+	TEST_SUCC(ioctl(disk_fd, BLKRRPART));
+}
+END_TEST()
+
+// The layout of the GPT:
+// - 1: the partition of the sectors [2048, 10240);
+// - 3: the partition of the sectors [20480, 24576).
+FN_SETUP(write_gpt)
+{
+	unsigned char *entry;
+
+	// The protective MBR.
+	set_mbr_entry(0, 0xee, 1, DISK_SECTORS - 1);
+	CHECK(write_sector(0));
+
+	memset(gpt_entries, 0, sizeof(gpt_entries));
+	entry = gpt_entries;
+	memset(entry, 0x11, 16);
+	put_u64(entry + 32, 2048);
+	put_u64(entry + 40, 10239);
+	entry = gpt_entries + 2 * GPT_ENTRY_SIZE;
+	memset(entry, 0x22, 16);
+	put_u64(entry + 32, 20480);
+	put_u64(entry + 40, 24575);
+	CHECK_WITH(pwrite(disk_fd, gpt_entries, sizeof(gpt_entries),
+			  2 * SECTOR_SIZE),
+		   _ret == sizeof(gpt_entries));
+
+	memcpy(sector, "EFI PART", 8);
+	put_u32(sector + 8, 0x00010000);
+	put_u32(sector + 12, 92);
+	put_u64(sector + 24, 1);
+	put_u64(sector + 32, DISK_SECTORS - 1);
+	put_u64(sector + 40, 2 + GPT_ENTRIES_SECTORS);
+	put_u64(sector + 48, DISK_SECTORS - 2 - GPT_ENTRIES_SECTORS);
+	put_u64(sector + 72, 2);
+	put_u32(sector + 80, GPT_ENTRIES);
+	put_u32(sector + 84, GPT_ENTRY_SIZE);
+	put_u32(sector + 88, crc32(gpt_entries, sizeof(gpt_entries)));
+	put_u32(sector + 16, crc32(sector, 92));
+	CHECK(write_sector(1));
+
+	CHECK(ioctl(disk_fd, BLKRRPART));
+}
+END_SETUP()
+
+FN_TEST(gpt_partitions)
+{
+	TEST_RES(partition_size("/dev/vda1"), _ret == 8192 * SECTOR_SIZE);
+	TEST_RES(partition_size("/dev/vda3"), _ret == 4096 * SECTOR_SIZE);
+	TEST_ERRNO(access("/dev/vda2", F_OK), ENOENT);
+	TEST_ERRNO(access("/dev/vda5", F_OK), ENOENT);
+}
+END_TEST()
+
+FN_TEST(corrupted_gpt)
+{
+	// The partitions are ignored if the checksum of the GPT header mismatches.
+	TEST_RES(pread(disk_fd, sector, SECTOR_SIZE, SECTOR_SIZE),
+		 _ret == SECTOR_SIZE);
+	sector[88] ^= 0xff;
+	TEST_SUCC(write_sector(1));
+
+	TEST_SUCC(ioctl(disk_fd, BLKRRPART));
+	TEST_ERRNO(access("/dev/vda1", F_OK), ENOENT);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	for (int sid = 0; sid < 2 + GPT_ENTRIES_SECTORS; sid++)
+		CHECK(write_sector(sid));
+	CHECK(ioctl(disk_fd, BLKRRPART));
+	CHECK(close(disk_fd));
+}
+END_SETUP()
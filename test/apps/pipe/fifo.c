@@ -0,0 +1,208 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <signal.h>
+#include <string.h>
+#include <sys/poll.h>
+#include <sys/stat.h>
+#include <sys/sysmacros.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define TMP_FIFO "/tmp/fifo_test"
+#define EXT2_FIFO "/ext2/fifo_test"
+#define TMP_CHR "/tmp/fifo_test_chr"
+#define EXT2_CHR "/ext2/fifo_test_chr"
+#define EXT2_BLK "/ext2/fifo_test_blk"
+
+FN_SETUP(create)
+{
+	signal(SIGPIPE, SIG_IGN);
+
+	CHECK(mkfifo(TMP_FIFO, 0666));
+	CHECK(mknodat(AT_FDCWD, EXT2_FIFO, S_IFIFO | 0666, 0));
+}
+END_SETUP()
+
+static int do_nonblocking_open(const char *path)
+{
+	int rfd, wfd;
+	char buf[8];
+
+	if (open(path, O_WRONLY | O_NONBLOCK) != -1 || errno != ENXIO)
+		return 0;
+
+	rfd = open(path, O_RDONLY | O_NONBLOCK);
+	if (rfd < 0)
+		return 0;
+	// There are no writers, so the reader sees the end of the file.
+	if (read(rfd, buf, sizeof(buf)) != 0)
+		return 0;
+
+	wfd = open(path, O_WRONLY | O_NONBLOCK);
+	if (wfd < 0)
+		return 0;
+	if (read(rfd, buf, sizeof(buf)) != -1 || errno != EAGAIN)
+		return 0;
+	if (write(wfd, "hello", 5) != 5)
+		return 0;
+	if (close(wfd) < 0)
+		return 0;
+
+	// The data are kept after the writer is closed.
+	if (read(rfd, buf, sizeof(buf)) != 5 || memcmp(buf, "hello", 5) != 0)
+		return 0;
+	if (read(rfd, buf, sizeof(buf)) != 0)
+		return 0;
+
+	return close(rfd) == 0;
+}
+
+FN_TEST(nonblocking_open)
+{
+	TEST_RES(do_nonblocking_open(TMP_FIFO), _ret);
+	TEST_RES(do_nonblocking_open(EXT2_FIFO), _ret);
+}
+END_TEST()
+
+static int do_blocking_open(const char *path, int reader_first)
+{
+	int fd, status;
+	char buf[8];
+	pid_t pid;
+
+	pid = fork();
+	if (pid < 0)
+		return 0;
+
+	if (pid == 0) {
+		usleep(100 * 1000);
+		fd = open(path, reader_first ? O_WRONLY : O_RDONLY);
+		if (fd < 0)
+			_exit(1);
+		if (reader_first && write(fd, "abc", 3) != 3)
+			_exit(1);
+		if (!reader_first &&
+		    (read(fd, buf, sizeof(buf)) != 3 || memcmp(buf, "abc", 3)))
+			_exit(1);
+		_exit(0);
+	}
+
+	// The open blocks until the child opens the other end.
+	fd = open(path, reader_first ? O_RDONLY : O_WRONLY);
+	if (fd < 0)
+		return 0;
+	if (reader_first &&
+	    (read(fd, buf, sizeof(buf)) != 3 || memcmp(buf, "abc", 3)))
+		return 0;
+	if (!reader_first && write(fd, "abc", 3) != 3)
+		return 0;
+
+	if (waitpid(pid, &status, 0) != pid || status != 0)
+		return 0;
+
+	// The child has closed its end.
+	if (reader_first && read(fd, buf, sizeof(buf)) != 0)
+		return 0;
+	if (!reader_first && (write(fd, "abc", 3) != -1 || errno != EPIPE))
+		return 0;
+
+	return close(fd) == 0;
+}
+
+FN_TEST(blocking_open)
+{
+	TEST_RES(do_blocking_open(TMP_FIFO, 1), _ret);
+	TEST_RES(do_blocking_open(TMP_FIFO, 0), _ret);
+	TEST_RES(do_blocking_open(EXT2_FIFO, 1), _ret);
+	TEST_RES(do_blocking_open(EXT2_FIFO, 0), _ret);
+}
+END_TEST()
+
+FN_TEST(read_write_open)
+{
+	int fd, fd2;
+	char buf[8];
+	struct pollfd pfd;
+
+	// Opening for both reading and writing never blocks.
+	fd = TEST_SUCC(open(TMP_FIFO, O_RDWR));
+	fd2 = TEST_SUCC(open(TMP_FIFO, O_WRONLY));
+
+	pfd.fd = fd;
+	pfd.events = POLLIN | POLLOUT;
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && pfd.revents == POLLOUT);
+
+	TEST_RES(write(fd2, "xyz", 3), _ret == 3);
+	TEST_RES(poll(&pfd, 1, 0),
+		 _ret == 1 && pfd.revents == (POLLIN | POLLOUT));
+	TEST_RES(read(fd, buf, sizeof(buf)), _ret == 3);
+
+	TEST_ERRNO(lseek(fd, 0, SEEK_SET), ESPIPE);
+	TEST_ERRNO(pread(fd, buf, sizeof(buf), 0), ESPIPE);
+
+	TEST_SUCC(close(fd2));
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(buffer_discarded)
+{
+	int fd;
+	char buf[8];
+
+	fd = TEST_SUCC(open(TMP_FIFO, O_RDWR));
+	TEST_RES(write(fd, "abc", 3), _ret == 3);
+	TEST_SUCC(close(fd));
+
+	// The data are discarded when all the ends are closed.
+	fd = TEST_SUCC(open(TMP_FIFO, O_RDONLY | O_NONBLOCK));
+	TEST_RES(read(fd, buf, sizeof(buf)), _ret == 0);
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(mknod_device)
+{
+	struct stat st;
+	int fd;
+
+	// A device node can be created for an unsupported device number,
+	// but it cannot be opened.
+	TEST_SUCC(mknod(TMP_CHR, S_IFCHR | 0666, makedev(240, 7)));
+	TEST_RES(stat(TMP_CHR, &st),
+		 S_ISCHR(st.st_mode) && major(st.st_rdev) == 240 &&
+			 minor(st.st_rdev) == 7);
+	TEST_ERRNO(open(TMP_CHR, O_RDONLY), ENXIO);
+	TEST_SUCC(unlink(TMP_CHR));
+
+	TEST_SUCC(mknod(EXT2_BLK, S_IFBLK | 0666, makedev(240, 8)));
+	TEST_RES(stat(EXT2_BLK, &st),
+		 S_ISBLK(st.st_mode) && major(st.st_rdev) == 240 &&
+			 minor(st.st_rdev) == 8);
+	TEST_ERRNO(open(EXT2_BLK, O_RDONLY), ENXIO);
+	TEST_SUCC(unlink(EXT2_BLK));
+
+	// A device node on ext2 opens the device with the device number.
+	TEST_SUCC(mknod(EXT2_CHR, S_IFCHR | 0666, makedev(1, 3)));
+	fd = TEST_SUCC(open(EXT2_CHR, O_RDWR));
+	TEST_RES(write(fd, "abc", 3), _ret == 3);
+	TEST_RES(read(fd, &st, sizeof(st)), _ret == 0);
+	TEST_SUCC(close(fd));
+	TEST_SUCC(unlink(EXT2_CHR));
+
+	TEST_ERRNO(mknod(TMP_CHR, S_IFDIR | 0666, 0), EPERM);
+	TEST_ERRNO(mknod(TMP_CHR, S_IFLNK | 0666, 0), EINVAL);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(unlink(TMP_FIFO));
+	CHECK(unlink(EXT2_FIFO));
+}
+END_SETUP()
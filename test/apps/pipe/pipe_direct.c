@@ -0,0 +1,85 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+#include <fcntl.h>
+#include <signal.h>
+#include <string.h>
+#include <unistd.h>
+
+static int fildes[2];
+
+FN_SETUP(pipe)
+{
+	signal(SIGPIPE, SIG_IGN);
+
+	CHECK(pipe2(fildes, O_DIRECT | O_NONBLOCK));
+}
+END_SETUP()
+
+FN_TEST(packet_boundaries)
+{
+	char buf[16];
+
+	TEST_RES(write(fildes[1], "hello", 5), _ret == 5);
+	TEST_RES(write(fildes[1], "world", 5), _ret == 5);
+
+	// Each read consumes at most one packet.
+	TEST_RES(read(fildes[0], buf, sizeof(buf)),
+		 _ret == 5 && memcmp(buf, "hello", 5) == 0);
+
+	// The remaining bytes of a packet are discarded.
+	TEST_RES(read(fildes[0], buf, 2),
+		 _ret == 2 && memcmp(buf, "wo", 2) == 0);
+	TEST_ERRNO(read(fildes[0], buf, sizeof(buf)), EAGAIN);
+}
+END_TEST()
+
+FN_TEST(packet_status_flags)
+{
+	TEST_RES(fcntl(fildes[0], F_GETFL), _ret & O_DIRECT);
+	TEST_RES(fcntl(fildes[1], F_GETFL), _ret & O_DIRECT);
+}
+END_TEST()
+
+FN_TEST(pipe_size)
+{
+	char buf[4096] = { 0 };
+
+	TEST_RES(fcntl(fildes[0], F_GETPIPE_SZ), _ret == 65536);
+
+	// The size is rounded up to a power of two.
+	TEST_RES(fcntl(fildes[1], F_SETPIPE_SZ, 5000), _ret == 8192);
+	TEST_RES(fcntl(fildes[0], F_GETPIPE_SZ), _ret == 8192);
+	TEST_RES(fcntl(fildes[1], F_SETPIPE_SZ, 1), _ret == 4096);
+
+	// The size cannot be smaller than the data in the pipe.
+	TEST_RES(fcntl(fildes[1], F_SETPIPE_SZ, 8192), _ret == 8192);
+	TEST_RES(write(fildes[1], buf, sizeof(buf)), _ret == sizeof(buf));
+	TEST_RES(write(fildes[1], buf, 1), _ret == 1);
+	TEST_ERRNO(fcntl(fildes[1], F_SETPIPE_SZ, 4096), EBUSY);
+	TEST_RES(read(fildes[0], buf, sizeof(buf)), _ret == sizeof(buf));
+	TEST_RES(read(fildes[0], buf, sizeof(buf)), _ret == 1);
+
+	TEST_ERRNO(fcntl(STDIN_FILENO, F_GETPIPE_SZ), EBADF);
+}
+END_TEST()
+
+FN_TEST(write_closed_pipe)
+{
+	int fds[2];
+
+	CHECK(pipe2(fds, O_DIRECT));
+	TEST_SUCC(close(fds[0]));
+	TEST_ERRNO(write(fds[1], "x", 1), EPIPE);
+	TEST_SUCC(close(fds[1]));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(fildes[0]));
+	CHECK(close(fildes[1]));
+}
+END_SETUP()
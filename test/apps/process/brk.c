@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/resource.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define PAGE_SIZE 4096
+
+static unsigned long page_align_up(unsigned long addr)
+{
+	return (addr + PAGE_SIZE - 1) & ~(unsigned long)(PAGE_SIZE - 1);
+}
+
+static unsigned long do_brk(unsigned long addr)
+{
+	return syscall(SYS_brk, addr);
+}
+
+static unsigned long init_brk;
+
+FN_SETUP(init_brk)
+{
+	init_brk = CHECK(do_brk(0));
+}
+END_SETUP()
+
+FN_TEST(grow_and_shrink)
+{
+	unsigned long new_brk = init_brk + 4 * PAGE_SIZE + 123;
+
+	// The program break is not necessarily page-aligned.
+	TEST_RES(do_brk(new_brk), _ret == new_brk);
+	TEST_RES(do_brk(0), _ret == new_brk);
+	memset((void *)init_brk, 0xaa, new_brk - init_brk);
+
+	TEST_RES(do_brk(init_brk + PAGE_SIZE), _ret == init_brk + PAGE_SIZE);
+	TEST_RES(do_brk(new_brk), _ret == new_brk);
+	TEST_RES(((unsigned char *)init_brk)[0], _ret == 0xaa);
+	// The removed pages are zeroed when the heap grows again.
+	TEST_RES(*(unsigned char *)page_align_up(init_brk + PAGE_SIZE),
+		 _ret == 0);
+
+	TEST_RES(do_brk(init_brk), _ret == init_brk);
+}
+END_TEST()
+
+FN_TEST(below_base)
+{
+	TEST_RES(do_brk(PAGE_SIZE), _ret == init_brk);
+	TEST_RES(do_brk(0), _ret == init_brk);
+}
+END_TEST()
+
+FN_TEST(mapping_in_the_way)
+{
+	void *addr = (void *)(page_align_up(init_brk) + 2 * PAGE_SIZE);
+
+	TEST_RES((long)mmap(addr, PAGE_SIZE, PROT_READ,
+			    MAP_PRIVATE | MAP_ANONYMOUS | MAP_FIXED_NOREPLACE,
+			    -1, 0),
+		 _ret == (long)addr);
+
+	// The program break stays unchanged if the heap cannot grow.
+	TEST_RES(do_brk(init_brk + 4 * PAGE_SIZE), _ret == init_brk);
+	TEST_RES(do_brk(init_brk + PAGE_SIZE), _ret == init_brk + PAGE_SIZE);
+
+	TEST_SUCC(munmap(addr, PAGE_SIZE));
+	TEST_RES(do_brk(init_brk + 4 * PAGE_SIZE),
+		 _ret == init_brk + 4 * PAGE_SIZE);
+	TEST_RES(do_brk(init_brk), _ret == init_brk);
+}
+END_TEST()
+
+FN_TEST(data_rlimit)
+{
+	struct rlimit old, new;
+
+	TEST_SUCC(getrlimit(RLIMIT_DATA, &old));
+	new.rlim_cur = 1024 * 1024;
+	new.rlim_max = old.rlim_max;
+	TEST_SUCC(setrlimit(RLIMIT_DATA, &new));
+
+	TEST_RES(do_brk(init_brk + 2 * 1024 * 1024), _ret == init_brk);
+	TEST_RES(do_brk(init_brk + PAGE_SIZE), _ret == init_brk + PAGE_SIZE);
+
+	TEST_SUCC(setrlimit(RLIMIT_DATA, &old));
+	TEST_RES(do_brk(init_brk + 2 * 1024 * 1024),
+		 _ret == init_brk + 2 * 1024 * 1024);
+	TEST_RES(do_brk(init_brk), _ret == init_brk);
+}
+END_TEST()
@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <linux/kcmp.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/prctl.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define FILE_NAME "/tmp/checkpoint_file"
+#define PAGE_SIZE 4096
+
+static int kcmp(pid_t pid1, pid_t pid2, int type, unsigned long idx1,
+		unsigned long idx2)
+{
+	return syscall(SYS_kcmp, pid1, pid2, type, idx1, idx2);
+}
+
+static void *map_pages(size_t len, int flags, int fd)
+{
+	return (void *)CHECK_WITH((long)mmap(NULL, len, PROT_READ, flags, fd,
+					     0),
+				  _ret != (long)MAP_FAILED);
+}
+
+static int readlink_map_file(void *start, size_t len, char *buf,
+			     size_t buf_len)
+{
+	char path[64];
+	int ret;
+
+	snprintf(path, sizeof(path), "/proc/self/map_files/%lx-%lx",
+		 (unsigned long)start, (unsigned long)start + len);
+	ret = readlink(path, buf, buf_len - 1);
+	if (ret < 0)
+		return -1;
+	buf[ret] = '\0';
+	return ret;
+}
+
+FN_TEST(map_files)
+{
+	char buf[64];
+	void *addr;
+	int fd;
+
+	fd = TEST_SUCC(open(FILE_NAME, O_RDWR | O_CREAT | O_TRUNC, 0644));
+	TEST_SUCC(ftruncate(fd, PAGE_SIZE * 2));
+	addr = map_pages(PAGE_SIZE * 2, MAP_SHARED, fd);
+
+	TEST_RES(readlink_map_file(addr, PAGE_SIZE * 2, buf, sizeof(buf)),
+		 strcmp(buf, FILE_NAME) == 0);
+
+	// The file is still linked after the file descriptor is closed.
+	TEST_SUCC(close(fd));
+	TEST_RES(readlink_map_file(addr, PAGE_SIZE * 2, buf, sizeof(buf)),
+		 strcmp(buf, FILE_NAME) == 0);
+
+	// The range must match a mapping exactly.
+	TEST_ERRNO(readlink_map_file(addr, PAGE_SIZE, buf, sizeof(buf)),
+		   ENOENT);
+	TEST_ERRNO(readlink("/proc/self/map_files/0-1000", buf, sizeof(buf)),
+		   ENOENT);
+
+	// Anonymous mappings have no entries.
+	TEST_SUCC(munmap(addr, PAGE_SIZE * 2));
+	addr = map_pages(PAGE_SIZE, MAP_PRIVATE | MAP_ANONYMOUS, -1);
+	TEST_ERRNO(readlink_map_file(addr, PAGE_SIZE, buf, sizeof(buf)),
+		   ENOENT);
+
+	TEST_SUCC(munmap(addr, PAGE_SIZE));
+	TEST_SUCC(unlink(FILE_NAME));
+}
+END_TEST()
+
+FN_TEST(kcmp_files)
+{
+	pid_t self = getpid();
+	int fd1, fd2, fd3;
+
+	fd1 = TEST_SUCC(open("/dev/null", O_RDONLY));
+	fd2 = TEST_SUCC(dup(fd1));
+	fd3 = TEST_SUCC(open("/dev/null", O_RDONLY));
+
+	TEST_RES(kcmp(self, self, KCMP_FILE, fd1, fd2), _ret == 0);
+	TEST_RES(kcmp(self, self, KCMP_FILE, fd1, fd3), _ret == 1 || _ret == 2);
+	// The order is consistent.
+	TEST_RES(kcmp(self, self, KCMP_FILE, fd1, fd3) +
+			 kcmp(self, self, KCMP_FILE, fd3, fd1),
+		 _ret == 3);
+
+	TEST_ERRNO(kcmp(self, self, KCMP_FILE, fd1, 1000), EBADF);
+	TEST_ERRNO(kcmp(self, self, KCMP_TYPES, 0, 0), EINVAL);
+	TEST_ERRNO(kcmp(self, 0x7fffffff, KCMP_VM, 0, 0), ESRCH);
+
+	TEST_SUCC(close(fd1));
+	TEST_SUCC(close(fd2));
+	TEST_SUCC(close(fd3));
+}
+END_TEST()
+
+FN_TEST(kcmp_child)
+{
+	pid_t self = getpid();
+	int pipefd[2];
+	pid_t pid;
+	int status;
+
+	TEST_SUCC(pipe(pipefd));
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		char c;
+		CHECK(read(pipefd[0], &c, 1));
+		_exit(0);
+	}
+
+	TEST_RES(kcmp(self, self, KCMP_VM, 0, 0), _ret == 0);
+	TEST_RES(kcmp(self, pid, KCMP_VM, 0, 0), _ret == 1 || _ret == 2);
+	TEST_RES(kcmp(self, pid, KCMP_FILES, 0, 0), _ret == 1 || _ret == 2);
+	TEST_RES(kcmp(self, pid, KCMP_SIGHAND, 0, 0), _ret == 1 || _ret == 2);
+
+	// The file descriptors in the child refer to the same files.
+	TEST_RES(kcmp(self, pid, KCMP_FILE, pipefd[0], pipefd[0]), _ret == 0);
+	TEST_RES(kcmp(self, pid, KCMP_FILE, pipefd[0], pipefd[1]),
+		 _ret == 1 || _ret == 2);
+
+	TEST_SUCC(write(pipefd[1], "a", 1));
+	TEST_RES(waitpid(pid, &status, 0), _ret == pid && WIFEXITED(status));
+	TEST_SUCC(close(pipefd[0]));
+	TEST_SUCC(close(pipefd[1]));
+}
+END_TEST()
+
+FN_TEST(set_mm)
+{
+	unsigned long brk = (unsigned long)sbrk(0);
+
+	TEST_SUCC(prctl(PR_SET_MM, PR_SET_MM_BRK, brk, 0, 0));
+	TEST_RES((unsigned long)sbrk(0), _ret == brk);
+
+	TEST_ERRNO(prctl(PR_SET_MM, PR_SET_MM_BRK, brk, 1, 0), EINVAL);
+	TEST_ERRNO(prctl(PR_SET_MM, PR_SET_MM_BRK, -PAGE_SIZE, 0, 0), EINVAL);
+}
+END_TEST()
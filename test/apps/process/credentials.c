@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <grp.h>
+#include <linux/capability.h>
+#include <signal.h>
+#include <sys/mount.h>
+#include <sys/resource.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define USER_UID 1000
+#define USER_GID 1000
+#define EXTRA_GID 2000
+#define OTHER_GID 3000
+
+#define TEST_DIR "/tmp/credentials_test"
+#define ROOT_FILE TEST_DIR "/root_file"
+#define USER_FILE TEST_DIR "/user_file"
+#define GROUP_FILE TEST_DIR "/group_file"
+
+static void create_file(const char *path, mode_t mode, uid_t uid, gid_t gid)
+{
+	int fd;
+
+	fd = CHECK(open(path, O_WRONLY | O_CREAT | O_TRUNC, mode));
+	CHECK(fchmod(fd, mode));
+	CHECK(fchown(fd, uid, gid));
+	CHECK(close(fd));
+}
+
+FN_SETUP(files)
+{
+	CHECK_WITH(mkdir(TEST_DIR, 0755), _ret == 0 || errno == EEXIST);
+	create_file(ROOT_FILE, 0600, 0, 0);
+	create_file(USER_FILE, 0600, USER_UID, USER_GID);
+	create_file(GROUP_FILE, 0060, 0, EXTRA_GID);
+}
+END_SETUP()
+
+static int get_caps(struct __user_cap_data_struct data[2])
+{
+	struct __user_cap_header_struct header = {
+		.version = _LINUX_CAPABILITY_VERSION_3,
+		.pid = 0,
+	};
+
+	return syscall(SYS_capget, &header, data);
+}
+
+static int set_caps(struct __user_cap_data_struct data[2])
+{
+	struct __user_cap_header_struct header = {
+		.version = _LINUX_CAPABILITY_VERSION_3,
+		.pid = 0,
+	};
+
+	return syscall(SYS_capset, &header, data);
+}
+
+// Runs the function in a child process that switches to a non-root user.
+static int run_as_user(void (*func)(void))
+{
+	int status;
+	pid_t pid;
+
+	pid = fork();
+	if (pid < 0)
+		return -1;
+	if (pid == 0) {
+		gid_t groups[] = { EXTRA_GID };
+
+		CHECK(setgroups(1, groups));
+		CHECK(setresgid(USER_GID, USER_GID, USER_GID));
+		CHECK(setresuid(USER_UID, USER_UID, USER_UID));
+		func();
+		exit(EXIT_SUCCESS);
+	}
+
+	if (waitpid(pid, &status, 0) != pid)
+		return -1;
+	return WIFEXITED(status) ? WEXITSTATUS(status) : -1;
+}
+
+FN_TEST(root_overrides_permissions)
+{
+	// `CAP_DAC_OVERRIDE` bypasses the permission bits, except for executing a file
+	// without any execute bits.
+	TEST_RES(open(USER_FILE, O_RDWR), _ret >= 0 && close(_ret) == 0);
+	TEST_RES(open(GROUP_FILE, O_RDWR), _ret >= 0 && close(_ret) == 0);
+	TEST_ERRNO(access(USER_FILE, X_OK), EACCES);
+}
+END_TEST()
+
+static void user_caps(void)
+{
+	struct __user_cap_data_struct data[2];
+
+	// Switching to a non-root user drops all the capabilities.
+	CHECK_WITH(get_caps(data), _ret == 0 && data[0].permitted == 0 &&
+					   data[0].effective == 0 &&
+					   data[1].permitted == 0 &&
+					   data[1].effective == 0);
+
+	// The capabilities cannot be raised.
+	data[0].permitted = data[0].effective = 1 << CAP_KILL;
+	CHECK_WITH(set_caps(data), _ret < 0 && errno == EPERM);
+}
+
+FN_TEST(drop_capabilities)
+{
+	struct __user_cap_data_struct data[2];
+
+	TEST_RES(get_caps(data), data[0].effective & (1 << CAP_SETUID));
+	TEST_RES(run_as_user(user_caps), _ret == 0);
+}
+END_TEST()
+
+static void user_ids(void)
+{
+	gid_t groups[2];
+
+	CHECK_WITH(getgroups(2, groups),
+		   _ret == 1 && groups[0] == EXTRA_GID);
+	CHECK_WITH(setgroups(0, NULL), _ret < 0 && errno == EPERM);
+	CHECK_WITH(setuid(0), _ret < 0 && errno == EPERM);
+	CHECK_WITH(setgid(0), _ret < 0 && errno == EPERM);
+	CHECK_WITH(setresuid(-1, 0, -1), _ret < 0 && errno == EPERM);
+	CHECK(setuid(USER_UID));
+}
+
+FN_TEST(change_ids)
+{
+	TEST_RES(run_as_user(user_ids), _ret == 0);
+}
+END_TEST()
+
+static void user_files(void)
+{
+	CHECK_WITH(open(ROOT_FILE, O_RDONLY), _ret < 0 && errno == EACCES);
+	CHECK_WITH(open(USER_FILE, O_RDWR), _ret >= 0 && close(_ret) == 0);
+	// The supplementary groups are checked.
+	CHECK_WITH(open(GROUP_FILE, O_RDWR), _ret >= 0 && close(_ret) == 0);
+
+	// Only the group can be changed to one of the groups of the owner.
+	CHECK_WITH(chown(USER_FILE, 0, -1), _ret < 0 && errno == EPERM);
+	CHECK_WITH(chown(USER_FILE, -1, OTHER_GID),
+		   _ret < 0 && errno == EPERM);
+	CHECK(chown(USER_FILE, USER_UID, EXTRA_GID));
+	CHECK(chown(USER_FILE, -1, USER_GID));
+	CHECK_WITH(chown(ROOT_FILE, -1, EXTRA_GID),
+		   _ret < 0 && errno == EPERM);
+}
+
+FN_TEST(access_files)
+{
+	TEST_RES(run_as_user(user_files), _ret == 0);
+}
+END_TEST()
+
+static void user_privileged_ops(void)
+{
+	// The parent process runs as root.
+	CHECK_WITH(kill(getppid(), 0), _ret < 0 && errno == EPERM);
+	CHECK(kill(getpid(), 0));
+
+	CHECK_WITH(setpriority(PRIO_PROCESS, getppid(), 0),
+		   _ret < 0 && errno == EPERM);
+	CHECK_WITH(setpriority(PRIO_PROCESS, 0, -1),
+		   _ret < 0 && errno == EACCES);
+	CHECK(setpriority(PRIO_PROCESS, 0, 1));
+
+	CHECK_WITH(mount("none", TEST_DIR, "tmpfs", 0, NULL),
+		   _ret < 0 && errno == EPERM);
+	CHECK_WITH(umount("/tmp"), _ret < 0 && errno == EPERM);
+}
+
+FN_TEST(privileged_operations)
+{
+	TEST_RES(run_as_user(user_privileged_ops), _ret == 0);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(unlink(ROOT_FILE));
+	CHECK(unlink(USER_FILE));
+	CHECK(unlink(GROUP_FILE));
+	CHECK(rmdir(TEST_DIR));
+}
+END_SETUP()
@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <pty.h>
+#include <signal.h>
+#include <sys/ioctl.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static int pipe_fds[2], sync_fds[2];
+
+static void report_sighup(int signum)
+{
+	(void)signum;
+
+	if (write(pipe_fds[1], "H", 1) != 1)
+		_exit(EXIT_FAILURE);
+	_exit(EXIT_SUCCESS);
+}
+
+static void catch_sighup(void)
+{
+	struct sigaction action = { .sa_handler = report_sighup };
+
+	CHECK(sigaction(SIGHUP, &action, NULL));
+}
+
+static int wait_for_exit(pid_t pid)
+{
+	int status;
+
+	if (waitpid(pid, &status, 0) != pid)
+		return -1;
+	return WIFEXITED(status) && WEXITSTATUS(status) == EXIT_SUCCESS;
+}
+
+FN_TEST(orphaned_stopped_group)
+{
+	char buf[1];
+	pid_t pid;
+
+	TEST_SUCC(pipe(pipe_fds));
+	TEST_SUCC(pipe(sync_fds));
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		// The session leader is in a different process group of the
+		// same session, so the process group below is not orphaned
+		// until its child exits.
+		CHECK(setsid());
+
+		if (CHECK(fork()) != 0) {
+			CHECK_WITH(wait_for_exit(-1), _ret == 1);
+			// Wait for the grandchild to report `SIGHUP`
+			pause();
+		}
+
+		CHECK(setpgid(0, 0));
+
+		if (CHECK(fork()) == 0) {
+			catch_sighup();
+			CHECK_WITH(write(sync_fds[1], "S", 1), _ret == 1);
+			raise(SIGSTOP);
+			// `SIGHUP` should be delivered before we're continued
+			_exit(EXIT_FAILURE);
+		}
+
+		// Exit after the grandchild is stopped, which makes its
+		// process group orphaned
+		CHECK_WITH(read(sync_fds[0], buf, 1),
+			   _ret == 1 && buf[0] == 'S');
+		usleep(100 * 1000);
+		exit(EXIT_SUCCESS);
+	}
+
+	TEST_RES(read(pipe_fds[0], buf, 1), _ret == 1 && buf[0] == 'H');
+
+	TEST_SUCC(kill(pid, SIGKILL));
+	TEST_RES(waitpid(pid, NULL, 0), _ret == pid);
+	TEST_SUCC(close(pipe_fds[0]));
+	TEST_SUCC(close(pipe_fds[1]));
+	TEST_SUCC(close(sync_fds[0]));
+	TEST_SUCC(close(sync_fds[1]));
+}
+END_TEST()
+
+FN_TEST(session_leader_exit)
+{
+	int master, slave;
+	char buf[1];
+	pid_t pid;
+
+	TEST_SUCC(pipe(pipe_fds));
+	TEST_SUCC(pipe(sync_fds));
+	TEST_SUCC(openpty(&master, &slave, NULL, NULL, NULL));
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK(setsid());
+		CHECK(ioctl(slave, TIOCSCTTY, 0));
+
+		if (CHECK(fork()) == 0) {
+			// We're in the foreground process group, so we should
+			// receive `SIGHUP` when the session leader exits.
+			catch_sighup();
+			CHECK_WITH(write(sync_fds[1], "R", 1), _ret == 1);
+			for (;;)
+				pause();
+		}
+
+		CHECK_WITH(read(sync_fds[0], buf, 1),
+			   _ret == 1 && buf[0] == 'R');
+		exit(EXIT_SUCCESS);
+	}
+
+	TEST_RES(wait_for_exit(pid), _ret == 1);
+	TEST_RES(read(pipe_fds[0], buf, 1), _ret == 1 && buf[0] == 'H');
+
+	TEST_SUCC(close(master));
+	TEST_SUCC(close(slave));
+	TEST_SUCC(close(pipe_fds[0]));
+	TEST_SUCC(close(pipe_fds[1]));
+	TEST_SUCC(close(sync_fds[0]));
+	TEST_SUCC(close(sync_fds[1]));
+}
+END_TEST()
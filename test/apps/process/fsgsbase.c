@@ -0,0 +1,158 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <asm/prctl.h>
+#include <pthread.h>
+#include <signal.h>
+#include <sched.h>
+#include <sys/auxv.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#ifndef HWCAP2_FSGSBASE
+#define HWCAP2_FSGSBASE (1 << 1)
+#endif
+
+static unsigned long gs_area[2] = { 0x1234, 0x5678 };
+
+static int arch_prctl(int code, unsigned long addr)
+{
+	return syscall(SYS_arch_prctl, code, addr);
+}
+
+static unsigned long get_fs(void)
+{
+	unsigned long fs = 0;
+
+	CHECK(arch_prctl(ARCH_GET_FS, (unsigned long)&fs));
+	return fs;
+}
+
+static unsigned long get_gs(void)
+{
+	unsigned long gs = 0;
+
+	CHECK(arch_prctl(ARCH_GET_GS, (unsigned long)&gs));
+	return gs;
+}
+
+static unsigned long read_gs_word(void)
+{
+	unsigned long val;
+
+	asm volatile("mov %%gs:0, %0" : "=r"(val));
+	return val;
+}
+
+FN_TEST(get_fs)
+{
+	// The FS base points to the thread control block, which is the thread descriptor in glibc.
+	TEST_RES(get_fs(), _ret == (unsigned long)pthread_self());
+}
+END_TEST()
+
+FN_TEST(set_gs)
+{
+	TEST_SUCC(arch_prctl(ARCH_SET_GS, (unsigned long)gs_area));
+	TEST_RES(get_gs(), _ret == (unsigned long)gs_area);
+	TEST_RES(read_gs_word(), _ret == 0x1234);
+}
+END_TEST()
+
+FN_TEST(invalid_args)
+{
+	TEST_ERRNO(arch_prctl(ARCH_SET_FS, 0xffff800000000000UL), EPERM);
+	TEST_ERRNO(arch_prctl(ARCH_SET_GS, 0xffff800000000000UL), EPERM);
+	TEST_ERRNO(arch_prctl(ARCH_GET_FS, 0), EFAULT);
+	TEST_ERRNO(arch_prctl(0x9999, 0), EINVAL);
+}
+END_TEST()
+
+FN_TEST(context_switch)
+{
+	for (int i = 0; i < 100; ++i)
+		sched_yield();
+
+	TEST_RES(get_fs(), _ret == (unsigned long)pthread_self());
+	TEST_RES(get_gs(), _ret == (unsigned long)gs_area);
+	TEST_RES(read_gs_word(), _ret == 0x1234);
+}
+END_TEST()
+
+static volatile unsigned long gs_in_handler;
+
+static void signal_handler(int signum)
+{
+	gs_in_handler = read_gs_word();
+}
+
+FN_TEST(signal)
+{
+	struct sigaction sa = { .sa_handler = signal_handler };
+
+	TEST_SUCC(sigaction(SIGUSR1, &sa, NULL));
+	TEST_SUCC(raise(SIGUSR1));
+
+	// The bases are not changed by the signal delivery or the signal return.
+	TEST_RES(gs_in_handler, _ret == 0x1234);
+	TEST_RES(get_fs(), _ret == (unsigned long)pthread_self());
+	TEST_RES(get_gs(), _ret == (unsigned long)gs_area);
+}
+END_TEST()
+
+FN_TEST(fork)
+{
+	int pid, status;
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		if (get_gs() != (unsigned long)gs_area ||
+		    read_gs_word() != 0x1234)
+			_exit(1);
+		_exit(0);
+	}
+
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+}
+END_TEST()
+
+static void *thread_gs(void *arg)
+{
+	return (void *)get_gs();
+}
+
+FN_TEST(thread)
+{
+	pthread_t thread;
+	void *gs;
+
+	// New threads inherit the GS base of the parent thread.
+	TEST_SUCC(pthread_create(&thread, NULL, thread_gs, NULL));
+	TEST_RES(pthread_join(thread, &gs), gs == gs_area);
+}
+END_TEST()
+
+FN_TEST(fsgsbase_instructions)
+{
+	unsigned long gs = (unsigned long)&gs_area[1];
+
+	// The instructions can be used only if the kernel enables them.
+	if (getauxval(AT_HWCAP2) & HWCAP2_FSGSBASE) {
+		// The kernel can observe the GS base set by the user space directly.
+		asm volatile("wrgsbase %0" : : "r"(gs));
+		TEST_RES(get_gs(), _ret == gs);
+		TEST_RES(read_gs_word(), _ret == 0x5678);
+
+		for (int i = 0; i < 100; ++i)
+			sched_yield();
+		TEST_RES(read_gs_word(), _ret == 0x5678);
+
+		TEST_SUCC(arch_prctl(ARCH_SET_GS, (unsigned long)gs_area));
+	}
+}
+END_TEST()
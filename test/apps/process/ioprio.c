@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <signal.h>
+#include <unistd.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+
+#define IOPRIO_CLASS_SHIFT 13
+#define IOPRIO_PRIO_VALUE(class, level) (((class) << IOPRIO_CLASS_SHIFT) | (level))
+
+#define IOPRIO_CLASS_NONE 0
+#define IOPRIO_CLASS_RT 1
+#define IOPRIO_CLASS_BE 2
+#define IOPRIO_CLASS_IDLE 3
+
+#define IOPRIO_WHO_PROCESS 1
+#define IOPRIO_WHO_PGRP 2
+#define IOPRIO_WHO_USER 3
+
+static int ioprio_set(int which, int who, int ioprio)
+{
+	return syscall(SYS_ioprio_set, which, who, ioprio);
+}
+
+static int ioprio_get(int which, int who)
+{
+	return syscall(SYS_ioprio_get, which, who);
+}
+
+FN_TEST(set_and_get)
+{
+	int be3 = IOPRIO_PRIO_VALUE(IOPRIO_CLASS_BE, 3);
+	int idle = IOPRIO_PRIO_VALUE(IOPRIO_CLASS_IDLE, 0);
+	int rt0 = IOPRIO_PRIO_VALUE(IOPRIO_CLASS_RT, 0);
+
+	TEST_SUCC(ioprio_set(IOPRIO_WHO_PROCESS, 0, be3));
+	TEST_RES(ioprio_get(IOPRIO_WHO_PROCESS, 0), _ret == be3);
+	TEST_RES(ioprio_get(IOPRIO_WHO_PROCESS, gettid()), _ret == be3);
+
+	TEST_SUCC(ioprio_set(IOPRIO_WHO_PROCESS, gettid(), idle));
+	TEST_RES(ioprio_get(IOPRIO_WHO_PROCESS, 0), _ret == idle);
+
+	// The tests run as root, which has `CAP_SYS_NICE`.
+	TEST_SUCC(ioprio_set(IOPRIO_WHO_PROCESS, 0, rt0));
+	TEST_RES(ioprio_get(IOPRIO_WHO_PROCESS, 0), _ret == rt0);
+
+	TEST_SUCC(ioprio_set(IOPRIO_WHO_PROCESS, 0, be3));
+}
+END_TEST()
+
+FN_TEST(invalid_args)
+{
+	int be3 = IOPRIO_PRIO_VALUE(IOPRIO_CLASS_BE, 3);
+
+	TEST_ERRNO(ioprio_set(IOPRIO_WHO_PROCESS, 0,
+			      IOPRIO_PRIO_VALUE(IOPRIO_CLASS_NONE, 1)),
+		   EINVAL);
+	TEST_ERRNO(ioprio_set(IOPRIO_WHO_PROCESS, 0, IOPRIO_PRIO_VALUE(4, 0)),
+		   EINVAL);
+
+	TEST_ERRNO(ioprio_set(0, 0, be3), EINVAL);
+	TEST_ERRNO(ioprio_get(4, 0), EINVAL);
+
+	TEST_ERRNO(ioprio_set(IOPRIO_WHO_PROCESS, 0x3fffffff, be3), ESRCH);
+	TEST_ERRNO(ioprio_get(IOPRIO_WHO_PROCESS, 0x3fffffff), ESRCH);
+
+	TEST_RES(ioprio_get(IOPRIO_WHO_PROCESS, 0), _ret == be3);
+}
+END_TEST()
+
+FN_TEST(inherit_and_highest)
+{
+	int be3 = IOPRIO_PRIO_VALUE(IOPRIO_CLASS_BE, 3);
+	int be6 = IOPRIO_PRIO_VALUE(IOPRIO_CLASS_BE, 6);
+	int pid, status;
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		pause();
+		_exit(0);
+	}
+
+	// The child inherits the I/O priority of the parent.
+	TEST_RES(ioprio_get(IOPRIO_WHO_PROCESS, pid), _ret == be3);
+	TEST_SUCC(ioprio_set(IOPRIO_WHO_PROCESS, pid, be6));
+	TEST_RES(ioprio_get(IOPRIO_WHO_PROCESS, pid), _ret == be6);
+
+	// The highest I/O priority of the processes is returned.
+	TEST_RES(ioprio_get(IOPRIO_WHO_PGRP, 0), _ret == be3);
+	TEST_RES(ioprio_get(IOPRIO_WHO_USER, 0), _ret != be6);
+
+	TEST_SUCC(kill(pid, SIGKILL));
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSIGNALED(status) &&
+			 WTERMSIG(status) == SIGKILL);
+}
+END_TEST()
@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#include "../network/test.h"
+
+#include <signal.h>
+#include <termios.h>
+#include <unistd.h>
+#include <pty.h>
+#include <sys/ioctl.h>
+#include <sys/wait.h>
+
+static int master, slave;
+static volatile sig_atomic_t received_signal;
+
+static void record_signal(int signum)
+{
+	received_signal = signum;
+}
+
+static void catch_signal(int signum)
+{
+	// No `SA_RESTART`, so the interrupted system call fails with `EINTR`
+	struct sigaction action = { .sa_handler = record_signal };
+
+	CHECK(sigaction(signum, &action, NULL));
+	received_signal = 0;
+}
+
+FN_SETUP(openpty)
+{
+	CHECK(openpty(&master, &slave, NULL, NULL, NULL));
+}
+END_SETUP()
+
+FN_SETUP(run_in_background)
+{
+	int status;
+
+	if (CHECK(fork()) != 0) {
+		CHECK_WITH(wait(&status),
+			   WIFEXITED(status) && WEXITSTATUS(status) == 0);
+		exit(EXIT_SUCCESS);
+	}
+
+	// The session leader stays in the foreground process group
+	CHECK(setsid());
+	CHECK(ioctl(slave, TIOCSCTTY, 0));
+
+	if (CHECK(fork()) != 0) {
+		CHECK_WITH(wait(&status),
+			   WIFEXITED(status) && WEXITSTATUS(status) == 0);
+		exit(EXIT_SUCCESS);
+	}
+
+	// The child runs in a background process group. Its parent is in
+	// the same session, so the process group is not orphaned.
+	CHECK(setpgid(0, 0));
+}
+END_SETUP()
+
+FN_TEST(background_read)
+{
+	char buf[1];
+
+	// `SIGTTIN` is ignored, so the read fails with `EIO`
+	signal(SIGTTIN, SIG_IGN);
+	TEST_ERRNO(read(slave, buf, sizeof(buf)), EIO);
+
+	// `SIGTTIN` is caught, so the read is interrupted
+	catch_signal(SIGTTIN);
+	TEST(read(slave, buf, sizeof(buf)), EINTR, received_signal == SIGTTIN);
+
+	signal(SIGTTIN, SIG_DFL);
+}
+END_TEST()
+
+FN_TEST(background_write)
+{
+	struct termios termios;
+
+	// Without `TOSTOP`, writes from the background are allowed
+	TEST_RES(write(slave, "a", 1), _ret == 1);
+	TEST_SUCC(tcgetattr(slave, &termios));
+
+	// Changing the terminal attributes generates `SIGTTOU`
+	catch_signal(SIGTTOU);
+	termios.c_lflag |= TOSTOP;
+	TEST(tcsetattr(slave, TCSANOW, &termios), EINTR,
+	     received_signal == SIGTTOU);
+
+	// ... unless `SIGTTOU` is ignored
+	signal(SIGTTOU, SIG_IGN);
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &termios));
+	TEST_RES(write(slave, "b", 1), _ret == 1);
+
+	// With `TOSTOP`, writes from the background generate `SIGTTOU`
+	catch_signal(SIGTTOU);
+	TEST(write(slave, "c", 1), EINTR, received_signal == SIGTTOU);
+
+	signal(SIGTTOU, SIG_IGN);
+	termios.c_lflag &= ~TOSTOP;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &termios));
+	signal(SIGTTOU, SIG_DFL);
+}
+END_TEST()
+
+FN_TEST(background_set_foreground)
+{
+	pid_t pgid = getpgrp();
+
+	// Setting the foreground process group generates `SIGTTOU`
+	catch_signal(SIGTTOU);
+	TEST(tcsetpgrp(slave, pgid), EINTR, received_signal == SIGTTOU);
+	TEST_RES(tcgetpgrp(slave), _ret != pgid);
+
+	// ... unless `SIGTTOU` is ignored
+	signal(SIGTTOU, SIG_IGN);
+	TEST_SUCC(tcsetpgrp(slave, pgid));
+	TEST_RES(tcgetpgrp(slave), _ret == pgid);
+
+	// Now we're in the foreground, so no signals are generated
+	catch_signal(SIGTTOU);
+	TEST_SUCC(tcsetpgrp(slave, pgid));
+	TEST_RES(write(slave, "d", 1), _ret == 1 && received_signal == 0);
+	signal(SIGTTOU, SIG_DFL);
+}
+END_TEST()
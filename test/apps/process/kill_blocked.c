@@ -0,0 +1,165 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <poll.h>
+#include <pthread.h>
+#include <signal.h>
+#include <time.h>
+#include <unistd.h>
+#include <linux/futex.h>
+#include <sys/ipc.h>
+#include <sys/sem.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+
+static long long now_ms(void)
+{
+	struct timespec ts;
+
+	clock_gettime(CLOCK_MONOTONIC, &ts);
+	return ts.tv_sec * 1000LL + ts.tv_nsec / 1000000;
+}
+
+// Kills the child after it has blocked and checks that it is terminated by `SIGKILL`.
+static int kill_and_reap(pid_t pid)
+{
+	int status;
+
+	usleep(100 * 1000);
+	if (kill(pid, SIGKILL) < 0)
+		return -1;
+	if (waitpid(pid, &status, 0) != pid)
+		return -1;
+
+	return WIFSIGNALED(status) && WTERMSIG(status) == SIGKILL ? 0 : -1;
+}
+
+FN_TEST(kill_with_all_signals_blocked)
+{
+	int fds[2];
+	char buf;
+	sigset_t mask;
+	pid_t pid;
+
+	TEST_SUCC(pipe(fds));
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		sigfillset(&mask);
+		CHECK(sigprocmask(SIG_SETMASK, &mask, NULL));
+		CHECK(read(fds[0], &buf, 1));
+		_exit(EXIT_FAILURE);
+	}
+	TEST_SUCC(kill_and_reap(pid));
+
+	TEST_SUCC(close(fds[0]));
+	TEST_SUCC(close(fds[1]));
+}
+END_TEST()
+
+FN_TEST(kill_in_ppoll_with_sigmask)
+{
+	sigset_t mask;
+	pid_t pid;
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		sigfillset(&mask);
+		CHECK(ppoll(NULL, 0, NULL, &mask));
+		_exit(EXIT_FAILURE);
+	}
+	TEST_SUCC(kill_and_reap(pid));
+}
+END_TEST()
+
+static int futex_word;
+
+static void *wait_futex(void *arg)
+{
+	syscall(SYS_futex, &futex_word, FUTEX_WAIT, 0, NULL, NULL, 0);
+	return NULL;
+}
+
+FN_TEST(exit_group_with_blocked_threads)
+{
+	pthread_t thread;
+	pid_t pid;
+	int status;
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		if (pthread_create(&thread, NULL, wait_futex, NULL) != 0)
+			_exit(EXIT_FAILURE);
+		usleep(100 * 1000);
+		// The thread blocked in `FUTEX_WAIT` should be killed.
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+}
+END_TEST()
+
+FN_TEST(kill_in_vfork)
+{
+	pid_t pid;
+	long long start;
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		// The parent should not wait for the child any longer once it is killed.
+		if (vfork() == 0) {
+			sleep(2);
+			_exit(EXIT_SUCCESS);
+		}
+		_exit(EXIT_FAILURE);
+	}
+
+	start = now_ms();
+	TEST_SUCC(kill_and_reap(pid));
+	TEST_RES(now_ms() - start, _ret < 1000);
+}
+END_TEST()
+
+static void do_nothing(int signum)
+{
+}
+
+FN_TEST(semop_interrupted)
+{
+	int sem_id;
+	struct sembuf op = { .sem_num = 0, .sem_op = -1, .sem_flg = 0 };
+	struct sigaction action = { .sa_handler = do_nothing };
+	pid_t pid;
+	int status;
+
+	sem_id = TEST_SUCC(semget(IPC_PRIVATE, 1, IPC_CREAT | 0600));
+
+	// The semaphore operation is interrupted by a signal.
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK(sigaction(SIGUSR1, &action, NULL));
+		if (semop(sem_id, &op, 1) < 0 && errno == EINTR)
+			_exit(EXIT_SUCCESS);
+		_exit(EXIT_FAILURE);
+	}
+	usleep(100 * 1000);
+	TEST_SUCC(kill(pid, SIGUSR1));
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+
+	// The semaphore operation is cancelled because the thread is killed.
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK(semop(sem_id, &op, 1));
+		_exit(EXIT_FAILURE);
+	}
+	TEST_SUCC(kill_and_reap(pid));
+
+	TEST_SUCC(semctl(sem_id, 0, IPC_RMID));
+}
+END_TEST()
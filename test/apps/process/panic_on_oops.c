@@ -0,0 +1,58 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+#define PANIC_ON_OOPS_PATH "/proc/sys/kernel/panic_on_oops"
+
+static int read_param(void)
+{
+	char buf[32] = { 0 };
+	int fd;
+
+	fd = CHECK(open(PANIC_ON_OOPS_PATH, O_RDONLY));
+	CHECK(read(fd, buf, sizeof(buf) - 1));
+	CHECK(close(fd));
+
+	return atoi(buf);
+}
+
+static int write_param(const char *value)
+{
+	int fd, ret, err;
+
+	fd = CHECK(open(PANIC_ON_OOPS_PATH, O_WRONLY));
+	ret = write(fd, value, strlen(value));
+	err = errno;
+	CHECK(close(fd));
+	errno = err;
+
+	return ret;
+}
+
+FN_TEST(panic_on_oops)
+{
+	int old_value;
+
+	old_value = read_param();
+	TEST_RES(old_value, _ret == 0 || _ret == 1);
+
+	TEST_ERRNO(write_param("oops"), EINVAL);
+	TEST_RES(read_param(), _ret == old_value);
+
+	// Any non-zero value is accepted.
+	TEST_SUCC(write_param("2\n"));
+	TEST_RES(read_param(), _ret == 1);
+	TEST_SUCC(write_param("0"));
+	TEST_RES(read_param(), _ret == 0);
+
+	TEST_SUCC(write_param(old_value ? "1" : "0"));
+	TEST_RES(read_param(), _ret == old_value);
+}
+END_TEST()
@@ -0,0 +1,208 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define PAGE_SIZE 4096
+
+#define FILE_A "/tmp/procfs_pid_a"
+#define FILE_B "/tmp/procfs_pid_b"
+
+static char buf[16384];
+
+static int read_proc(const char *path)
+{
+	int fd, len;
+
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+	len = read(fd, buf, sizeof(buf) - 1);
+	close(fd);
+	if (len < 0)
+		return -1;
+	buf[len] = '\0';
+
+	return len;
+}
+
+static const char *find_line(const char *prefix)
+{
+	const char *line = buf;
+
+	while (line != NULL && *line != '\0') {
+		if (strncmp(line, prefix, strlen(prefix)) == 0)
+			return line;
+		line = strchr(line, '\n');
+		if (line != NULL)
+			++line;
+	}
+
+	return NULL;
+}
+
+static int fd_link_is(int fd, const char *target)
+{
+	char path[64], link[256];
+	int len;
+
+	snprintf(path, sizeof(path), "/proc/self/fd/%d", fd);
+	len = readlink(path, link, sizeof(link) - 1);
+	if (len < 0)
+		return 0;
+	link[len] = '\0';
+
+	return strncmp(link, target, strlen(target)) == 0;
+}
+
+static long vm_size_kb(void)
+{
+	const char *line = find_line("VmSize:");
+	unsigned long size;
+
+	if (line == NULL || sscanf(line, "VmSize: %lu kB", &size) != 1)
+		return -1;
+
+	return size;
+}
+
+struct io_stats {
+	unsigned long rchar, wchar, syscr, syscw;
+};
+
+static int read_io(struct io_stats *stats)
+{
+	int len;
+
+	len = read_proc("/proc/self/io");
+	if (len < 0)
+		return -1;
+	if (sscanf(buf, "rchar: %lu\nwchar: %lu\nsyscr: %lu\nsyscw: %lu\n",
+		   &stats->rchar, &stats->wchar, &stats->syscr,
+		   &stats->syscw) != 4)
+		return -1;
+
+	return len;
+}
+
+FN_TEST(status)
+{
+	char expected[128];
+
+	TEST_RES(read_proc("/proc/self/status"), _ret > 0);
+
+	snprintf(expected, sizeof(expected), "Pid:\t%d\n", getpid());
+	TEST_RES(strstr(buf, expected) != NULL, _ret);
+	snprintf(expected, sizeof(expected), "PPid:\t%d\n", getppid());
+	TEST_RES(strstr(buf, expected) != NULL, _ret);
+	TEST_RES(find_line("TracerPid:\t0\n") != NULL, _ret);
+
+	snprintf(expected, sizeof(expected), "Uid:\t%d\t%d\t", getuid(),
+		 geteuid());
+	TEST_RES(find_line(expected) != NULL, _ret);
+	snprintf(expected, sizeof(expected), "Gid:\t%d\t%d\t", getgid(),
+		 getegid());
+	TEST_RES(find_line(expected) != NULL, _ret);
+
+	TEST_RES(vm_size_kb(), _ret > 0);
+}
+END_TEST()
+
+FN_TEST(maps)
+{
+	char *addr;
+	char expected[64];
+
+	addr = (char *)TEST_SUCC((long)mmap(NULL, 4 * PAGE_SIZE, PROT_READ,
+					    MAP_PRIVATE | MAP_ANONYMOUS, -1,
+					    0));
+
+	TEST_RES(read_proc("/proc/self/maps"), _ret > 0);
+	snprintf(expected, sizeof(expected), "%08lx-%08lx r--p 00000000 ",
+		 (unsigned long)addr, (unsigned long)addr + 4 * PAGE_SIZE);
+	TEST_RES(strstr(buf, expected) != NULL, _ret);
+
+	// The entries are recomputed on each read.
+	TEST_SUCC(mprotect(addr, PAGE_SIZE, PROT_READ | PROT_WRITE));
+	TEST_RES(read_proc("/proc/self/maps"), _ret > 0);
+	snprintf(expected, sizeof(expected), "%08lx-%08lx rw-p 00000000 ",
+		 (unsigned long)addr, (unsigned long)addr + PAGE_SIZE);
+	TEST_RES(strstr(buf, expected) != NULL, _ret);
+
+	TEST_SUCC(munmap(addr, 4 * PAGE_SIZE));
+	TEST_RES(read_proc("/proc/self/maps"), _ret > 0);
+	snprintf(expected, sizeof(expected), "%08lx-", (unsigned long)addr);
+	TEST_RES(strstr(buf, expected) == NULL, _ret);
+}
+END_TEST()
+
+FN_TEST(fd)
+{
+	char path[64], link[256];
+	int fd_a, fd_b, pipe_fds[2];
+
+	fd_a = TEST_SUCC(open(FILE_A, O_RDWR | O_CREAT | O_TRUNC, 0644));
+	fd_b = TEST_SUCC(open(FILE_B, O_RDWR | O_CREAT | O_TRUNC, 0644));
+	TEST_RES(fd_link_is(fd_a, FILE_A), _ret);
+	TEST_RES(fd_link_is(fd_b, FILE_B), _ret);
+
+	// The links follow the file table.
+	TEST_SUCC(dup2(fd_b, fd_a));
+	TEST_RES(fd_link_is(fd_a, FILE_B), _ret);
+
+	TEST_SUCC(close(fd_a));
+	snprintf(path, sizeof(path), "/proc/self/fd/%d", fd_a);
+	TEST_ERRNO(readlink(path, link, sizeof(link)), ENOENT);
+	TEST_RES(fd_link_is(fd_b, FILE_B), _ret);
+
+	TEST_SUCC(pipe(pipe_fds));
+	TEST_RES(fd_link_is(pipe_fds[0], "pipe:["), _ret);
+	TEST_RES(fd_link_is(pipe_fds[1], "pipe:["), _ret);
+
+	TEST_SUCC(close(pipe_fds[0]));
+	TEST_SUCC(close(pipe_fds[1]));
+	TEST_SUCC(close(fd_b));
+	TEST_SUCC(unlink(FILE_A));
+	TEST_SUCC(unlink(FILE_B));
+}
+END_TEST()
+
+FN_TEST(io)
+{
+	struct io_stats before, after;
+	char data[100] = { 0 };
+	int fd, len;
+
+	TEST_RES(read_proc("/proc/self/io"), _ret > 0);
+	TEST_RES(find_line("read_bytes: ") != NULL, _ret);
+	TEST_RES(find_line("write_bytes: ") != NULL, _ret);
+	TEST_RES(find_line("cancelled_write_bytes: ") != NULL, _ret);
+
+	fd = TEST_SUCC(open(FILE_A, O_RDWR | O_CREAT | O_TRUNC, 0644));
+
+	// Nothing else can be done in between, since the test results are
+	// written to stderr.
+	len = read_io(&before);
+	if (write(fd, data, sizeof(data)) != sizeof(data) ||
+	    pread(fd, data, sizeof(data) / 2, 0) != sizeof(data) / 2)
+		len = -1;
+	TEST_RES(read_io(&after), _ret > 0 && len > 0);
+
+	// The read of `/proc/self/io` itself is accounted after the statistics
+	// are generated.
+	TEST_RES(after.syscr - before.syscr, _ret == 2);
+	TEST_RES(after.rchar - before.rchar, _ret == len + sizeof(data) / 2);
+	TEST_RES(after.syscw - before.syscw, _ret == 1);
+	TEST_RES(after.wchar - before.wchar, _ret == sizeof(data));
+
+	TEST_SUCC(close(fd));
+	TEST_SUCC(unlink(FILE_A));
+}
+END_TEST()
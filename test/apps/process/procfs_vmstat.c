@@ -0,0 +1,180 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define PAGE_SIZE 4096
+#define NR_PAGES 64
+
+#define FILE_NAME "/ext2/procfs_vmstat"
+
+static char buf[4096];
+
+static int read_proc(const char *path)
+{
+	int fd, len;
+
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+	len = read(fd, buf, sizeof(buf) - 1);
+	close(fd);
+	if (len < 0)
+		return -1;
+	buf[len] = '\0';
+
+	return len;
+}
+
+// Returns the value of the field in the form of `<name><sep><value>`, or -1 if the field is
+// absent.
+static long read_field(const char *path, const char *name)
+{
+	const char *line;
+	size_t len = strlen(name);
+
+	if (read_proc(path) < 0)
+		return -1;
+
+	for (line = buf; line != NULL && *line != '\0';) {
+		if (strncmp(line, name, len) == 0 &&
+		    (line[len] == ' ' || line[len] == ':'))
+			return atol(line + len + 1);
+		line = strchr(line, '\n');
+		if (line != NULL)
+			++line;
+	}
+
+	return -1;
+}
+
+static long vmstat(const char *name)
+{
+	return read_field("/proc/vmstat", name);
+}
+
+static long smaps_rollup(const char *path, const char *name)
+{
+	return read_field(path, name);
+}
+
+static char *addr;
+
+FN_SETUP(mmap_pages)
+{
+	addr = (char *)CHECK_WITH((long)mmap(NULL, PAGE_SIZE * NR_PAGES,
+					     PROT_READ | PROT_WRITE,
+					     MAP_PRIVATE | MAP_ANONYMOUS, -1,
+					     0),
+				  _ret != (long)MAP_FAILED);
+}
+END_SETUP()
+
+FN_TEST(vmstat_fields)
+{
+	TEST_RES(vmstat("nr_free_pages"), _ret > 0);
+	TEST_RES(vmstat("nr_dirty"), _ret >= 0);
+	TEST_RES(vmstat("nr_page_table_pages"), _ret > 0);
+	TEST_RES(vmstat("nr_dirtied"), _ret >= 0);
+	TEST_RES(vmstat("nr_written"), _ret >= 0);
+	TEST_RES(vmstat("pgfault"), _ret > 0);
+	TEST_RES(vmstat("pgmajfault"), _ret >= 0);
+	TEST_RES(vmstat("cow_ksm"), _ret >= 0);
+}
+END_TEST()
+
+FN_TEST(vmstat_pgfault)
+{
+	long before;
+	int i;
+
+	before = TEST_SUCC(vmstat("pgfault"));
+	for (i = 0; i < NR_PAGES; ++i)
+		addr[i * PAGE_SIZE] = 'a';
+	TEST_RES(vmstat("pgfault"), _ret >= before + NR_PAGES);
+}
+END_TEST()
+
+FN_TEST(vmstat_writeback)
+{
+	long dirtied, written;
+	int fd;
+
+	dirtied = TEST_SUCC(vmstat("nr_dirtied"));
+	written = TEST_SUCC(vmstat("nr_written"));
+
+	fd = TEST_SUCC(open(FILE_NAME, O_RDWR | O_CREAT | O_TRUNC, 0644));
+	TEST_RES(write(fd, addr, PAGE_SIZE * 4), _ret == PAGE_SIZE * 4);
+	TEST_RES(vmstat("nr_dirtied"), _ret >= dirtied + 4);
+	TEST_SUCC(fsync(fd));
+	TEST_RES(vmstat("nr_written"), _ret >= written + 4);
+
+	TEST_SUCC(close(fd));
+	TEST_SUCC(unlink(FILE_NAME));
+}
+END_TEST()
+
+FN_TEST(smaps_rollup_private)
+{
+	const char *path = "/proc/self/smaps_rollup";
+
+	TEST_RES(read_proc(path), strstr(buf, "[rollup]") != NULL);
+
+	// The pages touched by the previous test are private and dirty.
+	TEST_RES(smaps_rollup(path, "Rss"), _ret >= NR_PAGES * 4);
+	TEST_RES(smaps_rollup(path, "Pss"),
+		 _ret >= NR_PAGES * 4 && _ret <= smaps_rollup(path, "Rss"));
+	TEST_RES(smaps_rollup(path, "Private_Dirty"), _ret >= NR_PAGES * 4);
+	TEST_RES(smaps_rollup(path, "Anonymous"), _ret >= NR_PAGES * 4);
+	TEST_RES(smaps_rollup(path, "Pss_Anon"), _ret >= NR_PAGES * 4);
+}
+END_TEST()
+
+FN_TEST(smaps_rollup_shared)
+{
+	char path[64];
+	int pipefd[2];
+	long private_dirty;
+	int pid, status;
+	char c;
+
+	private_dirty = TEST_SUCC(
+		smaps_rollup("/proc/self/smaps_rollup", "Private_Dirty"));
+
+	TEST_SUCC(pipe(pipefd));
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		close(pipefd[1]);
+		// Wait until the parent finishes the checks.
+		exit(read(pipefd[0], &c, 1) < 0 ? EXIT_FAILURE : EXIT_SUCCESS);
+	}
+	TEST_SUCC(close(pipefd[0]));
+
+	// The pages are shared with the child until they are written.
+	snprintf(path, sizeof(path), "/proc/%d/smaps_rollup", pid);
+	TEST_RES(smaps_rollup(path, "Shared_Dirty"), _ret >= NR_PAGES * 4);
+	TEST_RES(smaps_rollup("/proc/self/smaps_rollup", "Private_Dirty"),
+		 _ret <= private_dirty - NR_PAGES * 4);
+	TEST_RES(smaps_rollup("/proc/self/smaps_rollup", "Pss"),
+		 _ret < smaps_rollup("/proc/self/smaps_rollup", "Rss"));
+
+	TEST_SUCC(close(pipefd[1]));
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(munmap(addr, PAGE_SIZE * NR_PAGES));
+}
+END_SETUP()
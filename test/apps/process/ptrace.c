@@ -0,0 +1,182 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <elf.h>
+#include <signal.h>
+#include <unistd.h>
+#include <sys/ptrace.h>
+#include <sys/syscall.h>
+#include <sys/uio.h>
+#include <sys/user.h>
+#include <sys/wait.h>
+
+#if defined(__x86_64__)
+#define REG_SYSCALL_NR(regs) ((regs).orig_rax)
+#define REG_RETURN(regs) ((regs).rax)
+#elif defined(__riscv) && __riscv_xlen == 64
+#define REG_SYSCALL_NR(regs) ((regs).a7)
+#define REG_RETURN(regs) ((regs).a0)
+#endif
+
+static volatile long value = 1;
+
+static int get_regs(int pid, struct user_regs_struct *regs)
+{
+	struct iovec iov = { .iov_base = regs, .iov_len = sizeof(*regs) };
+
+	return ptrace(PTRACE_GETREGSET, pid, NT_PRSTATUS, &iov);
+}
+
+static int wait_stopped(int pid, int status)
+{
+	int wstatus;
+
+	if (waitpid(pid, &wstatus, 0) != pid)
+		return -1;
+	if (!WIFSTOPPED(wstatus) || (wstatus >> 8) != status) {
+		errno = EINVAL;
+		return -1;
+	}
+	return 0;
+}
+
+FN_TEST(trace_me)
+{
+	struct user_regs_struct regs;
+	int pid, status;
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK(ptrace(PTRACE_TRACEME, 0, NULL, NULL));
+		raise(SIGSTOP);
+		syscall(SYS_getpid);
+		_exit(value == 2 ? 0 : 1);
+	}
+
+	// The signal-delivery-stop of `SIGSTOP`
+	TEST_SUCC(wait_stopped(pid, SIGSTOP));
+
+	TEST_RES(ptrace(PTRACE_PEEKDATA, pid, &value, NULL), _ret == 1);
+	TEST_SUCC(ptrace(PTRACE_POKEDATA, pid, &value, (void *)2));
+	TEST_RES(ptrace(PTRACE_PEEKDATA, pid, &value, NULL), _ret == 2);
+	TEST_ERRNO(ptrace(PTRACE_PEEKDATA, pid, NULL, NULL), EIO);
+
+	TEST_ERRNO(ptrace(PTRACE_SETOPTIONS, pid, NULL, (void *)0x80000000),
+		   EINVAL);
+	TEST_ERRNO(ptrace(0xdead, pid, NULL, NULL), EIO);
+	TEST_SUCC(ptrace(PTRACE_SETOPTIONS, pid, NULL,
+			 (void *)PTRACE_O_TRACESYSGOOD));
+
+	// The syscall-enter-stop of `getpid`
+	do {
+		TEST_SUCC(ptrace(PTRACE_SYSCALL, pid, NULL, NULL));
+		TEST_SUCC(wait_stopped(pid, SIGTRAP | 0x80));
+		TEST_SUCC(get_regs(pid, &regs));
+	} while (REG_SYSCALL_NR(regs) != SYS_getpid);
+#if defined(__x86_64__)
+	TEST_RES(REG_RETURN(regs), _ret == -ENOSYS);
+#endif
+
+	// The syscall-exit-stop of `getpid`
+	TEST_SUCC(ptrace(PTRACE_SYSCALL, pid, NULL, NULL));
+	TEST_SUCC(wait_stopped(pid, SIGTRAP | 0x80));
+	TEST_SUCC(get_regs(pid, &regs));
+	TEST_RES(REG_SYSCALL_NR(regs), _ret == SYS_getpid);
+	TEST_RES(REG_RETURN(regs), _ret == pid);
+
+	TEST_SUCC(ptrace(PTRACE_CONT, pid, NULL, NULL));
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+}
+END_TEST()
+
+FN_TEST(attach_and_detach)
+{
+	siginfo_t siginfo;
+	int pid, status;
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		for (;;)
+			pause();
+	}
+
+	TEST_ERRNO(ptrace(PTRACE_PEEKDATA, pid, &value, NULL), ESRCH);
+	TEST_ERRNO(ptrace(PTRACE_ATTACH, getpid(), NULL, NULL), EPERM);
+
+	TEST_SUCC(ptrace(PTRACE_ATTACH, pid, NULL, NULL));
+	TEST_ERRNO(ptrace(PTRACE_ATTACH, pid, NULL, NULL), EPERM);
+
+	// The tracee is stopped by `SIGSTOP`.
+	TEST_SUCC(wait_stopped(pid, SIGSTOP));
+	TEST_RES(ptrace(PTRACE_GETSIGINFO, pid, NULL, &siginfo),
+		 siginfo.si_signo == SIGSTOP);
+
+	// The tracee continues to run after being detached.
+	TEST_SUCC(ptrace(PTRACE_DETACH, pid, NULL, NULL));
+	TEST_ERRNO(ptrace(PTRACE_CONT, pid, NULL, NULL), ESRCH);
+	TEST_RES(waitpid(pid, &status, WNOHANG), _ret == 0);
+
+	TEST_SUCC(kill(pid, SIGKILL));
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSIGNALED(status) &&
+			 WTERMSIG(status) == SIGKILL);
+}
+END_TEST()
+
+FN_TEST(seize_and_interrupt)
+{
+	int pid, status;
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		for (;;)
+			pause();
+	}
+
+	TEST_ERRNO(ptrace(PTRACE_SEIZE, pid, (void *)1, NULL), EIO);
+	TEST_ERRNO(ptrace(PTRACE_SEIZE, pid, NULL, (void *)0x80000000), EIO);
+	TEST_SUCC(ptrace(PTRACE_SEIZE, pid, NULL,
+			 (void *)PTRACE_O_TRACESYSGOOD));
+
+	// The tracee is not stopped.
+	TEST_RES(waitpid(pid, &status, WNOHANG), _ret == 0);
+	TEST_ERRNO(ptrace(PTRACE_CONT, pid, NULL, NULL), ESRCH);
+
+	// The tracee is stopped by the tracer.
+	TEST_SUCC(ptrace(PTRACE_INTERRUPT, pid, NULL, NULL));
+	TEST_SUCC(wait_stopped(pid, SIGTRAP | (PTRACE_EVENT_STOP << 8)));
+
+	TEST_SUCC(ptrace(PTRACE_CONT, pid, NULL, NULL));
+	TEST_RES(waitpid(pid, &status, WNOHANG), _ret == 0);
+
+	TEST_SUCC(kill(pid, SIGKILL));
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSIGNALED(status) &&
+			 WTERMSIG(status) == SIGKILL);
+}
+END_TEST()
+
+FN_TEST(interrupt_without_seize)
+{
+	int pid, status;
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		for (;;)
+			pause();
+	}
+
+	TEST_SUCC(ptrace(PTRACE_ATTACH, pid, NULL, NULL));
+	TEST_SUCC(wait_stopped(pid, SIGSTOP));
+	TEST_ERRNO(ptrace(PTRACE_INTERRUPT, pid, NULL, NULL), EIO);
+
+	TEST_SUCC(kill(pid, SIGKILL));
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSIGNALED(status) &&
+			 WTERMSIG(status) == SIGKILL);
+}
+END_TEST()
@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <linux/reboot.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static long do_reboot(unsigned int magic, unsigned int magic2, unsigned int cmd)
+{
+	return syscall(SYS_reboot, magic, magic2, cmd, NULL);
+}
+
+FN_TEST(invalid_magic)
+{
+	TEST_ERRNO(do_reboot(0, LINUX_REBOOT_MAGIC2, LINUX_REBOOT_CMD_POWER_OFF),
+		   EINVAL);
+	TEST_ERRNO(do_reboot(LINUX_REBOOT_MAGIC1, 0, LINUX_REBOOT_CMD_POWER_OFF),
+		   EINVAL);
+}
+END_TEST()
+
+FN_TEST(invalid_cmd)
+{
+	TEST_ERRNO(do_reboot(LINUX_REBOOT_MAGIC1, LINUX_REBOOT_MAGIC2,
+			     0x12345678),
+		   EINVAL);
+}
+END_TEST()
+
+FN_TEST(cad)
+{
+	TEST_SUCC(do_reboot(LINUX_REBOOT_MAGIC1, LINUX_REBOOT_MAGIC2C,
+			    LINUX_REBOOT_CMD_CAD_ON));
+	TEST_SUCC(do_reboot(LINUX_REBOOT_MAGIC1, LINUX_REBOOT_MAGIC2B,
+			    LINUX_REBOOT_CMD_CAD_OFF));
+}
+END_TEST()
+
+FN_TEST(no_capability)
+{
+	int status;
+	pid_t pid;
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		// Switching to a non-root user drops all the capabilities.
+		CHECK(setuid(65534));
+		CHECK_WITH(do_reboot(LINUX_REBOOT_MAGIC1, LINUX_REBOOT_MAGIC2,
+				     LINUX_REBOOT_CMD_POWER_OFF),
+			   _ret < 0 && errno == EPERM);
+		// The capability is checked before the magic numbers.
+		CHECK_WITH(do_reboot(0, 0, LINUX_REBOOT_CMD_POWER_OFF),
+			   _ret < 0 && errno == EPERM);
+		exit(EXIT_SUCCESS);
+	}
+
+	TEST_RES(wait4(pid, &status, 0, NULL),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+}
+END_TEST()
@@ -0,0 +1,427 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <linux/audit.h>
+#include <linux/filter.h>
+#include <linux/seccomp.h>
+#include <poll.h>
+#include <signal.h>
+#include <stddef.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <sys/prctl.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#if defined(__x86_64__)
+#define ARCH_NR AUDIT_ARCH_X86_64
+#elif defined(__riscv) && __riscv_xlen == 64
+#define ARCH_NR AUDIT_ARCH_RISCV64
+#endif
+
+#ifndef SYS_SECCOMP
+#define SYS_SECCOMP 1
+#endif
+
+#define TRAP_DATA 42
+#define MAGIC_FD 12345
+
+static int seccomp(unsigned int op, unsigned int flags, void *args)
+{
+	return syscall(SYS_seccomp, op, flags, args);
+}
+
+static int install_filter(struct sock_filter *filter, unsigned short len)
+{
+	struct sock_fprog prog = { .len = len, .filter = filter };
+
+	return seccomp(SECCOMP_SET_MODE_FILTER, 0, &prog);
+}
+
+#define LOAD(field) \
+	BPF_STMT(BPF_LD | BPF_W | BPF_ABS, offsetof(struct seccomp_data, field))
+#define LOAD_NR LOAD(nr)
+#define RET(action) BPF_STMT(BPF_RET | BPF_K, (action))
+
+// Fails `getppid` with `E2BIG`, fails `close(MAGIC_FD)` with `ENOENT`, traps
+// `getuid`, and kills the process on `getgid`.
+static struct sock_filter first_filter[] = {
+	LOAD(arch),
+	BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, ARCH_NR, 1, 0),
+	RET(SECCOMP_RET_KILL_PROCESS),
+	LOAD_NR,
+	BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, SYS_getppid, 0, 1),
+	RET(SECCOMP_RET_ERRNO | E2BIG),
+	BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, SYS_getuid, 0, 1),
+	RET(SECCOMP_RET_TRAP | TRAP_DATA),
+	BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, SYS_getgid, 0, 1),
+	RET(SECCOMP_RET_KILL_PROCESS),
+	BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, SYS_close, 0, 3),
+	LOAD(args[0]),
+	BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, MAGIC_FD, 0, 1),
+	RET(SECCOMP_RET_ERRNO | ENOENT),
+	RET(SECCOMP_RET_ALLOW),
+};
+
+// Fails `getpid` with `ESRCH`, and tries to allow `getppid`.
+static struct sock_filter second_filter[] = {
+	LOAD_NR,
+	BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, SYS_getpid, 0, 1),
+	RET(SECCOMP_RET_ERRNO | ESRCH),
+	RET(SECCOMP_RET_ALLOW),
+};
+
+// Runs the function in a child process and returns the wait status.
+static int run_in_child(void (*func)(void))
+{
+	int status;
+	pid_t pid;
+
+	pid = fork();
+	if (pid < 0)
+		return -1;
+	if (pid == 0) {
+		func();
+		exit(EXIT_SUCCESS);
+	}
+
+	if (waitpid(pid, &status, 0) != pid)
+		return -1;
+	return status;
+}
+
+static int exited_with_success(int status)
+{
+	return status >= 0 && WIFEXITED(status) &&
+	       WEXITSTATUS(status) == EXIT_SUCCESS;
+}
+
+static int killed_by(int status, int signum)
+{
+	return status >= 0 && WIFSIGNALED(status) && WTERMSIG(status) == signum;
+}
+
+FN_TEST(action_avail)
+{
+	unsigned int action;
+
+	action = SECCOMP_RET_ALLOW;
+	TEST_SUCC(seccomp(SECCOMP_GET_ACTION_AVAIL, 0, &action));
+	action = SECCOMP_RET_KILL_PROCESS;
+	TEST_SUCC(seccomp(SECCOMP_GET_ACTION_AVAIL, 0, &action));
+	action = SECCOMP_RET_USER_NOTIF;
+	TEST_SUCC(seccomp(SECCOMP_GET_ACTION_AVAIL, 0, &action));
+	action = 0x12340000;
+	TEST_ERRNO(seccomp(SECCOMP_GET_ACTION_AVAIL, 0, &action), EOPNOTSUPP);
+	TEST_ERRNO(seccomp(SECCOMP_GET_ACTION_AVAIL, 1, &action), EINVAL);
+}
+END_TEST()
+
+static void strict_write_and_exit(void)
+{
+	CHECK(prctl(PR_SET_SECCOMP, SECCOMP_MODE_STRICT));
+	CHECK(write(STDERR_FILENO, "", 0));
+	// `exit_group`, which is used by `exit`, is not allowed.
+	syscall(SYS_exit, EXIT_SUCCESS);
+}
+
+static void strict_getpid(void)
+{
+	CHECK(seccomp(SECCOMP_SET_MODE_STRICT, 0, NULL));
+	syscall(SYS_getpid);
+	syscall(SYS_exit, EXIT_SUCCESS);
+}
+
+FN_TEST(strict_mode)
+{
+	TEST_RES(run_in_child(strict_write_and_exit),
+		 exited_with_success(_ret));
+	TEST_RES(run_in_child(strict_getpid), killed_by(_ret, SIGKILL));
+	TEST_ERRNO(seccomp(SECCOMP_SET_MODE_STRICT, 1, NULL), EINVAL);
+}
+END_TEST()
+
+static void unprivileged_filter(void)
+{
+	CHECK(setresuid(65534, 65534, 65534));
+	CHECK_WITH(prctl(PR_GET_NO_NEW_PRIVS, 0, 0, 0, 0), _ret == 0);
+	CHECK_WITH(install_filter(second_filter, 4),
+		   _ret < 0 && errno == EACCES);
+
+	CHECK(prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0));
+	CHECK(install_filter(second_filter, 4));
+}
+
+FN_TEST(unprivileged)
+{
+	TEST_RES(run_in_child(unprivileged_filter), exited_with_success(_ret));
+}
+END_TEST()
+
+FN_TEST(invalid_filters)
+{
+	struct sock_filter no_ret[] = { LOAD_NR };
+	struct sock_filter bad_jump[] = {
+		LOAD_NR,
+		BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, 0, 1, 0),
+		RET(SECCOMP_RET_ALLOW),
+	};
+	struct sock_filter bad_offset[] = {
+		BPF_STMT(BPF_LD | BPF_W | BPF_ABS, 2),
+		RET(SECCOMP_RET_ALLOW),
+	};
+	struct sock_filter bad_size[] = {
+		BPF_STMT(BPF_LD | BPF_B | BPF_ABS, 0),
+		RET(SECCOMP_RET_ALLOW),
+	};
+	struct sock_filter bad_div[] = {
+		BPF_STMT(BPF_ALU | BPF_DIV | BPF_K, 0),
+		RET(SECCOMP_RET_ALLOW),
+	};
+
+	TEST_SUCC(prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0));
+	TEST_RES(prctl(PR_GET_NO_NEW_PRIVS, 0, 0, 0, 0), _ret == 1);
+
+	TEST_ERRNO(install_filter(no_ret, 0), EINVAL);
+	TEST_ERRNO(install_filter(no_ret, 1), EINVAL);
+	TEST_ERRNO(install_filter(bad_jump, 3), EINVAL);
+	TEST_ERRNO(install_filter(bad_offset, 2), EINVAL);
+	TEST_ERRNO(install_filter(bad_size, 2), EINVAL);
+	TEST_ERRNO(install_filter(bad_div, 2), EINVAL);
+	TEST_ERRNO(seccomp(SECCOMP_SET_MODE_FILTER, 1 << 30, NULL), EINVAL);
+	TEST_RES(prctl(PR_GET_SECCOMP), _ret == 0);
+}
+END_TEST()
+
+static volatile int trapped_syscall;
+static volatile int trapped_errno;
+static volatile unsigned int trapped_arch;
+
+static void handle_sigsys(int signum, siginfo_t *info, void *context)
+{
+	if (info->si_code == SYS_SECCOMP) {
+		trapped_syscall = info->si_syscall;
+		trapped_errno = info->si_errno;
+		trapped_arch = info->si_arch;
+	}
+}
+
+static void filtered_syscalls(void)
+{
+	struct sigaction action = {
+		.sa_sigaction = handle_sigsys,
+		.sa_flags = SA_SIGINFO,
+	};
+	int status;
+	pid_t pid;
+
+	CHECK(sigaction(SIGSYS, &action, NULL));
+	CHECK(install_filter(first_filter, sizeof(first_filter) /
+						   sizeof(first_filter[0])));
+	CHECK_WITH(prctl(PR_GET_SECCOMP), _ret == SECCOMP_MODE_FILTER);
+
+	CHECK_WITH(syscall(SYS_getppid), _ret < 0 && errno == E2BIG);
+	CHECK_WITH(close(MAGIC_FD), _ret < 0 && errno == ENOENT);
+	CHECK_WITH(close(MAGIC_FD + 1), _ret < 0 && errno == EBADF);
+
+	syscall(SYS_getuid);
+	CHECK_WITH(trapped_syscall, _ret == SYS_getuid);
+	CHECK_WITH(trapped_errno, _ret == TRAP_DATA);
+	CHECK_WITH(trapped_arch, _ret == ARCH_NR);
+
+	// The new filter cannot relax the old one.
+	CHECK(install_filter(second_filter, 4));
+	CHECK_WITH(syscall(SYS_getpid), _ret < 0 && errno == ESRCH);
+	CHECK_WITH(syscall(SYS_getppid), _ret < 0 && errno == E2BIG);
+
+	// The filters cannot be replaced by the strict mode.
+	CHECK_WITH(prctl(PR_SET_SECCOMP, SECCOMP_MODE_STRICT),
+		   _ret < 0 && errno == EINVAL);
+
+	// The child process inherits the filters.
+	pid = CHECK(fork());
+	if (pid == 0) {
+		if (syscall(SYS_getppid) >= 0 || errno != E2BIG)
+			_exit(EXIT_FAILURE);
+		_exit(EXIT_SUCCESS);
+	}
+	CHECK_WITH(waitpid(pid, &status, 0),
+		   _ret == pid && exited_with_success(status));
+}
+
+static void killed_syscall(void)
+{
+	CHECK(install_filter(first_filter, sizeof(first_filter) /
+						   sizeof(first_filter[0])));
+	syscall(SYS_getgid);
+}
+
+FN_TEST(filter_mode)
+{
+	TEST_RES(run_in_child(filtered_syscalls), exited_with_success(_ret));
+	TEST_RES(run_in_child(killed_syscall), killed_by(_ret, SIGSYS));
+}
+END_TEST()
+
+// Notifies the supervisor of `getppid`.
+static struct sock_filter notif_filter[] = {
+	LOAD_NR,
+	BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, SYS_getppid, 0, 1),
+	RET(SECCOMP_RET_USER_NOTIF),
+	RET(SECCOMP_RET_ALLOW),
+};
+
+#define NOTIF_VAL 4242
+
+static pid_t supervisor_pid;
+
+static int install_listener(unsigned int flags)
+{
+	struct sock_fprog prog = {
+		.len = sizeof(notif_filter) / sizeof(notif_filter[0]),
+		.filter = notif_filter,
+	};
+
+	return seccomp(SECCOMP_SET_MODE_FILTER,
+		       SECCOMP_FILTER_FLAG_NEW_LISTENER | flags, &prog);
+}
+
+// Receives a notification of `getppid` from `pid`.
+static int recv_notif(int listener, pid_t pid, struct seccomp_notif *notif)
+{
+	struct pollfd pfd = { .fd = listener, .events = POLLIN };
+
+	if (poll(&pfd, 1, -1) != 1 || pfd.revents != POLLIN)
+		return -1;
+
+	memset(notif, 0, sizeof(*notif));
+	if (ioctl(listener, SECCOMP_IOCTL_NOTIF_RECV, notif) < 0)
+		return -1;
+	if (notif->pid != pid || notif->data.nr != SYS_getppid ||
+	    notif->data.arch != ARCH_NR)
+		return -1;
+
+	// The notification is received but not responded to.
+	pfd.events = POLLIN | POLLOUT;
+	if (poll(&pfd, 1, 0) != 1 || pfd.revents != POLLOUT)
+		return -1;
+
+	return ioctl(listener, SECCOMP_IOCTL_NOTIF_ID_VALID, &notif->id);
+}
+
+static int send_resp(int listener, __u64 id, __s64 val, __s32 error,
+		     __u32 flags)
+{
+	struct seccomp_notif_resp resp = {
+		.id = id,
+		.val = val,
+		.error = error,
+		.flags = flags,
+	};
+
+	return ioctl(listener, SECCOMP_IOCTL_NOTIF_SEND, &resp);
+}
+
+static void notified_syscalls(void)
+{
+	int fd;
+
+	if (syscall(SYS_getppid) != NOTIF_VAL)
+		_exit(EXIT_FAILURE);
+	if (syscall(SYS_getppid) != -1 || errno != EPERM)
+		_exit(EXIT_FAILURE);
+	if (syscall(SYS_getppid) != supervisor_pid)
+		_exit(EXIT_FAILURE);
+
+	// The supervisor installs a file and responds with its descriptor.
+	fd = syscall(SYS_getppid);
+	if (fd < 0 || write(fd, "x", 1) != 1)
+		_exit(EXIT_FAILURE);
+
+	_exit(EXIT_SUCCESS);
+}
+
+static void supervise_syscalls(void)
+{
+	struct seccomp_notif notif;
+	struct seccomp_notif_addfd addfd = { 0 };
+	int listener, status, fds[2];
+	char buf;
+	pid_t pid;
+
+	CHECK(prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0));
+	supervisor_pid = getpid();
+	listener = CHECK(install_listener(0));
+	// Only one filter can have a listener.
+	CHECK_WITH(install_listener(0), _ret < 0 && errno == EBUSY);
+	CHECK(pipe(fds));
+
+	pid = CHECK(fork());
+	if (pid == 0)
+		notified_syscalls();
+
+	CHECK(recv_notif(listener, pid, &notif));
+	CHECK(send_resp(listener, notif.id, NOTIF_VAL, 0, 0));
+	// The notification is responded to only once.
+	CHECK_WITH(send_resp(listener, notif.id, 0, 0, 0),
+		   _ret < 0 && (errno == EINPROGRESS || errno == ENOENT));
+	CHECK_WITH(ioctl(listener, SECCOMP_IOCTL_NOTIF_ID_VALID, &notif.id),
+		   _ret < 0 && errno == ENOENT);
+
+	CHECK(recv_notif(listener, pid, &notif));
+	CHECK(send_resp(listener, notif.id, 0, -EPERM, 0));
+
+	CHECK(recv_notif(listener, pid, &notif));
+	CHECK_WITH(send_resp(listener, notif.id, 1, 0,
+			     SECCOMP_USER_NOTIF_FLAG_CONTINUE),
+		   _ret < 0 && errno == EINVAL);
+	CHECK(send_resp(listener, notif.id, 0, 0,
+			SECCOMP_USER_NOTIF_FLAG_CONTINUE));
+
+	CHECK(recv_notif(listener, pid, &notif));
+	addfd.id = notif.id;
+	addfd.srcfd = fds[1];
+	addfd.flags = SECCOMP_ADDFD_FLAG_SEND;
+	addfd.newfd_flags = O_NONBLOCK;
+	CHECK_WITH(ioctl(listener, SECCOMP_IOCTL_NOTIF_ADDFD, &addfd),
+		   _ret < 0 && errno == EINVAL);
+	addfd.newfd_flags = O_CLOEXEC;
+	CHECK(ioctl(listener, SECCOMP_IOCTL_NOTIF_ADDFD, &addfd));
+	CHECK_WITH(read(fds[0], &buf, 1), _ret == 1 && buf == 'x');
+
+	CHECK_WITH(waitpid(pid, &status, 0),
+		   _ret == pid && exited_with_success(status));
+
+	// The buffer must be zeroed.
+	memset(&notif, 0xff, sizeof(notif));
+	CHECK_WITH(ioctl(listener, SECCOMP_IOCTL_NOTIF_RECV, &notif),
+		   _ret < 0 && errno == EINVAL);
+
+	// Without the listener, the system call fails with `ENOSYS`.
+	CHECK(close(listener));
+	CHECK_WITH(syscall(SYS_getppid), _ret < 0 && errno == ENOSYS);
+}
+
+FN_TEST(user_notif)
+{
+	struct seccomp_notif_sizes sizes;
+
+	TEST_RES(seccomp(SECCOMP_GET_NOTIF_SIZES, 0, &sizes),
+		 sizes.seccomp_notif == sizeof(struct seccomp_notif) &&
+			 sizes.seccomp_notif_resp ==
+				 sizeof(struct seccomp_notif_resp) &&
+			 sizes.seccomp_data == sizeof(struct seccomp_data));
+
+	TEST_ERRNO(seccomp(SECCOMP_SET_MODE_FILTER,
+			   SECCOMP_FILTER_FLAG_WAIT_KILLABLE_RECV, NULL),
+		   EINVAL);
+	TEST_ERRNO(install_listener(SECCOMP_FILTER_FLAG_TSYNC), EINVAL);
+
+	TEST_RES(run_in_child(supervise_syscalls), exited_with_success(_ret));
+}
+END_TEST()
@@ -0,0 +1,296 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <grp.h>
+#include <linux/capability.h>
+#include <sched.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define USER_UID 1000
+#define USER_GID 1000
+#define OVERFLOW_ID 65534
+
+#define TEST_DIR "/tmp/user_ns_test"
+#define ROOT_FILE TEST_DIR "/root_file"
+#define USER_FILE TEST_DIR "/user_file"
+
+static void create_file(const char *path, mode_t mode, uid_t uid, gid_t gid)
+{
+	int fd;
+
+	fd = CHECK(open(path, O_WRONLY | O_CREAT | O_TRUNC, mode));
+	CHECK(fchmod(fd, mode));
+	CHECK(fchown(fd, uid, gid));
+	CHECK(close(fd));
+}
+
+FN_SETUP(files)
+{
+	CHECK_WITH(mkdir(TEST_DIR, 0755), _ret == 0 || errno == EEXIST);
+	create_file(ROOT_FILE, 0600, 0, 0);
+	create_file(USER_FILE, 0600, USER_UID, USER_GID);
+}
+END_SETUP()
+
+static int write_file(const char *path, const char *data)
+{
+	int fd, ret, err;
+
+	fd = CHECK(open(path, O_WRONLY));
+	ret = write(fd, data, strlen(data));
+	err = errno;
+	CHECK(close(fd));
+
+	errno = err;
+	return ret < 0 ? -1 : 0;
+}
+
+static int read_file(const char *path, char *buf, size_t len)
+{
+	int fd, ret;
+
+	fd = CHECK(open(path, O_RDONLY));
+	ret = read(fd, buf, len - 1);
+	CHECK(close(fd));
+
+	if (ret < 0)
+		return -1;
+	buf[ret] = '\0';
+	return ret;
+}
+
+static int has_cap(int cap)
+{
+	struct __user_cap_header_struct header = {
+		.version = _LINUX_CAPABILITY_VERSION_3,
+		.pid = 0,
+	};
+	struct __user_cap_data_struct data[2];
+
+	CHECK(syscall(SYS_capget, &header, data));
+	return !!(data[cap / 32].effective & (1U << (cap % 32)));
+}
+
+static uid_t file_uid(const char *path)
+{
+	struct stat st;
+
+	CHECK(stat(path, &st));
+	return st.st_uid;
+}
+
+// Runs the function in a child process, which switches to a non-root user if `as_user` is set.
+static int run_in_child(void (*func)(void), int as_user)
+{
+	int status;
+	pid_t pid;
+
+	pid = fork();
+	if (pid < 0)
+		return -1;
+	if (pid == 0) {
+		if (as_user) {
+			CHECK(setgroups(0, NULL));
+			CHECK(setresgid(USER_GID, USER_GID, USER_GID));
+			CHECK(setresuid(USER_UID, USER_UID, USER_UID));
+		}
+		func();
+		exit(EXIT_SUCCESS);
+	}
+
+	if (waitpid(pid, &status, 0) != pid)
+		return -1;
+	return WIFEXITED(status) ? WEXITSTATUS(status) : -1;
+}
+
+// Runs the function in a child process in a new user namespace, whose maps are written by the
+// current process.
+static int run_in_new_ns(void (*func)(void), const char *uid_map,
+			 const char *gid_map)
+{
+	char path[64];
+	int pipefd[2];
+	int status;
+	pid_t pid;
+	char c;
+
+	CHECK(pipe(pipefd));
+	pid = syscall(SYS_clone, CLONE_NEWUSER | SIGCHLD, 0, NULL, NULL, 0);
+	if (pid < 0)
+		return -1;
+	if (pid == 0) {
+		// Wait until the maps are written.
+		CHECK(close(pipefd[1]));
+		CHECK_WITH(read(pipefd[0], &c, 1), _ret == 0);
+		func();
+		exit(EXIT_SUCCESS);
+	}
+
+	CHECK(close(pipefd[0]));
+	snprintf(path, sizeof(path), "/proc/%d/uid_map", pid);
+	CHECK(write_file(path, uid_map));
+	snprintf(path, sizeof(path), "/proc/%d/gid_map", pid);
+	CHECK(write_file(path, gid_map));
+	CHECK(close(pipefd[1]));
+
+	if (waitpid(pid, &status, 0) != pid)
+		return -1;
+	return WIFEXITED(status) ? WEXITSTATUS(status) : -1;
+}
+
+FN_TEST(initial_maps)
+{
+	char buf[64];
+
+	TEST_RES(read_file("/proc/self/uid_map", buf, sizeof(buf)),
+		 strcmp(buf, "         0          0 4294967295\n") == 0);
+	TEST_RES(read_file("/proc/self/gid_map", buf, sizeof(buf)),
+		 strcmp(buf, "         0          0 4294967295\n") == 0);
+	TEST_RES(read_file("/proc/self/setgroups", buf, sizeof(buf)),
+		 strcmp(buf, "allow\n") == 0);
+
+	// The maps of the initial user namespace cannot be changed.
+	TEST_ERRNO(write_file("/proc/self/uid_map", "0 0 1\n"), EPERM);
+}
+END_TEST()
+
+static void unprivileged_ns(void)
+{
+	char buf[64];
+
+	CHECK(unshare(CLONE_NEWUSER));
+
+	// The IDs are not mapped yet, but all the capabilities are gained in the new namespace.
+	CHECK_WITH(getuid(), _ret == OVERFLOW_ID);
+	CHECK_WITH(getgid(), _ret == OVERFLOW_ID);
+	CHECK_WITH(file_uid(USER_FILE), _ret == OVERFLOW_ID);
+	CHECK_WITH(has_cap(CAP_SYS_ADMIN), _ret == 1);
+	CHECK_WITH(read_file("/proc/self/uid_map", buf, sizeof(buf)),
+		   _ret == 0);
+
+	// The capabilities are not in effect outside the new namespace.
+	CHECK_WITH(sethostname("userns", 6), _ret < 0 && errno == EPERM);
+	CHECK_WITH(open(ROOT_FILE, O_RDONLY), _ret < 0 && errno == EACCES);
+
+	// Only the own IDs can be mapped.
+	CHECK_WITH(write_file("/proc/self/uid_map", "0 0 1\n"),
+		   _ret < 0 && errno == EPERM);
+	CHECK_WITH(write_file("/proc/self/uid_map", "0 1000 2\n"),
+		   _ret < 0 && errno == EPERM);
+	CHECK_WITH(write_file("/proc/self/uid_map", "0 1000 0\n"),
+		   _ret < 0 && errno == EINVAL);
+	CHECK(write_file("/proc/self/uid_map", "0 1000 1\n"));
+	CHECK_WITH(write_file("/proc/self/uid_map", "0 1000 1\n"),
+		   _ret < 0 && errno == EPERM);
+	CHECK_WITH(read_file("/proc/self/uid_map", buf, sizeof(buf)),
+		   strcmp(buf, "         0       1000          1\n") == 0);
+
+	// The own group can only be mapped if `setgroups` is denied.
+	CHECK_WITH(write_file("/proc/self/gid_map", "0 1000 1\n"),
+		   _ret < 0 && errno == EPERM);
+	CHECK(write_file("/proc/self/setgroups", "deny"));
+	CHECK_WITH(write_file("/proc/self/setgroups", "allow"),
+		   _ret < 0 && errno == EPERM);
+	CHECK(write_file("/proc/self/gid_map", "0 1000 1\n"));
+	CHECK_WITH(write_file("/proc/self/setgroups", "deny"),
+		   _ret < 0 && errno == EPERM);
+	CHECK_WITH(setgroups(0, NULL), _ret < 0 && errno == EPERM);
+
+	// The user is the root user in the new namespace.
+	CHECK_WITH(getuid(), _ret == 0);
+	CHECK_WITH(getgid(), _ret == 0);
+	CHECK_WITH(file_uid(USER_FILE), _ret == 0);
+	CHECK_WITH(file_uid(ROOT_FILE), _ret == OVERFLOW_ID);
+	CHECK_WITH(setuid(1), _ret < 0 && errno == EINVAL);
+	CHECK_WITH(chown(USER_FILE, 1, -1), _ret < 0 && errno == EINVAL);
+	CHECK_WITH(open(ROOT_FILE, O_RDONLY), _ret < 0 && errno == EACCES);
+}
+
+FN_TEST(unprivileged_ns)
+{
+	TEST_RES(run_in_child(unprivileged_ns, 1), _ret == 0);
+}
+END_TEST()
+
+static void privileged_ns(void)
+{
+	CHECK_WITH(file_uid(ROOT_FILE), _ret == 0);
+	CHECK_WITH(file_uid(USER_FILE), _ret == 1);
+	CHECK_WITH(has_cap(CAP_SETUID), _ret == 1);
+
+	// The IDs in the new namespace are converted to and from the kernel IDs.
+	CHECK(setgroups(0, NULL));
+	CHECK(setresgid(1, 1, 1));
+	CHECK_WITH(setresuid(2, 2, 2), _ret < 0 && errno == EINVAL);
+	CHECK(setresuid(1, 1, 1));
+	CHECK_WITH(getuid(), _ret == 1);
+	CHECK_WITH(getgid(), _ret == 1);
+	CHECK_WITH(has_cap(CAP_SETUID), _ret == 0);
+
+	// The file of the user is accessible, but the file of the root user is not.
+	CHECK_WITH(open(USER_FILE, O_RDONLY), _ret >= 0 && close(_ret) == 0);
+	CHECK_WITH(open(ROOT_FILE, O_RDONLY), _ret < 0 && errno == EACCES);
+}
+
+static void overlapped_map(void)
+{
+	CHECK(unshare(CLONE_NEWUSER));
+
+	// The maps cannot be written in the new namespace without `CAP_SETUID` in the parent
+	// namespace, and the IDs must not overlap.
+	CHECK_WITH(write_file("/proc/self/uid_map", "0 0 1\n1 1000 1\n"),
+		   _ret < 0 && errno == EPERM);
+	CHECK_WITH(write_file("/proc/self/uid_map", "0 0 1\n0 1000 1\n"),
+		   _ret < 0 && errno == EINVAL);
+}
+
+FN_TEST(privileged_ns)
+{
+	// With `CAP_SETUID` and `CAP_SETGID` in the parent namespace, any IDs can be mapped.
+	TEST_RES(run_in_new_ns(privileged_ns, "0 0 1\n1 1000 1\n",
+			       "0 0 1\n1 1000 1\n"),
+		 _ret == 0);
+	TEST_RES(run_in_child(overlapped_map, 0), _ret == 0);
+}
+END_TEST()
+
+static void nested_ns(void)
+{
+	char buf[64];
+
+	CHECK(unshare(CLONE_NEWUSER));
+	CHECK(write_file("/proc/self/uid_map", "0 0 1\n"));
+
+	// The lower IDs of a nested namespace are the IDs in its parent namespace.
+	CHECK(unshare(CLONE_NEWUSER));
+	CHECK_WITH(write_file("/proc/self/uid_map", "5 0 1\n"), _ret == 0);
+	CHECK_WITH(read_file("/proc/self/uid_map", buf, sizeof(buf)),
+		   strcmp(buf, "         5          0          1\n") == 0);
+	CHECK_WITH(getuid(), _ret == 5);
+	CHECK_WITH(write_file("/proc/self/gid_map", "0 0 1\n"),
+		   _ret < 0 && errno == EPERM);
+}
+
+FN_TEST(nested_ns)
+{
+	TEST_RES(run_in_child(nested_ns, 0), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(clone_flags)
+{
+	// The file system information cannot be shared across user namespaces.
+	TEST_ERRNO(syscall(SYS_clone, CLONE_NEWUSER | CLONE_FS | SIGCHLD, 0,
+			   NULL, NULL, 0),
+		   EINVAL);
+}
+END_TEST()
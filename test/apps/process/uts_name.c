@@ -0,0 +1,123 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <poll.h>
+#include <string.h>
+#include <sys/epoll.h>
+#include <sys/utsname.h>
+#include <unistd.h>
+
+static struct utsname old_name;
+
+FN_SETUP(uname)
+{
+	CHECK(uname(&old_name));
+}
+END_SETUP()
+
+static int read_file_and_check(const char *path, const char *expected)
+{
+	char buf[128];
+	ssize_t len;
+	int fd;
+
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+	len = read(fd, buf, sizeof(buf));
+	close(fd);
+	if (len < 0)
+		return -1;
+
+	return len == strlen(expected) && memcmp(buf, expected, len) == 0;
+}
+
+FN_TEST(sethostname)
+{
+	struct utsname name;
+	char long_name[66];
+
+	TEST_SUCC(sethostname("asterinas-test", 14));
+	TEST_RES(uname(&name), strcmp(name.nodename, "asterinas-test") == 0);
+	TEST_RES(read_file_and_check("/proc/sys/kernel/hostname",
+				     "asterinas-test\n"),
+		 _ret == 1);
+
+	// The name does not need to be null-terminated.
+	TEST_SUCC(sethostname("abcdef", 3));
+	TEST_RES(uname(&name), strcmp(name.nodename, "abc") == 0);
+
+	memset(long_name, 'a', sizeof(long_name));
+	TEST_SUCC(sethostname(long_name, 64));
+	TEST_RES(uname(&name), strlen(name.nodename) == 64);
+	TEST_ERRNO(sethostname(long_name, 65), EINVAL);
+
+	TEST_SUCC(sethostname(old_name.nodename, strlen(old_name.nodename)));
+	TEST_RES(uname(&name), strcmp(name.nodename, old_name.nodename) == 0);
+}
+END_TEST()
+
+FN_TEST(setdomainname)
+{
+	struct utsname name;
+
+	TEST_SUCC(setdomainname("example.com", 11));
+	TEST_RES(uname(&name), strcmp(name.domainname, "example.com") == 0);
+	TEST_RES(read_file_and_check("/proc/sys/kernel/domainname",
+				     "example.com\n"),
+		 _ret == 1);
+
+	TEST_SUCC(setdomainname("", 0));
+	TEST_RES(uname(&name), name.domainname[0] == '\0');
+	TEST_RES(read_file_and_check("/proc/sys/kernel/domainname", "\n"),
+		 _ret == 1);
+
+	TEST_SUCC(setdomainname(old_name.domainname,
+				strlen(old_name.domainname)));
+}
+END_TEST()
+
+static int poll_pri(int fd)
+{
+	struct pollfd pfd = { .fd = fd, .events = POLLPRI };
+
+	if (poll(&pfd, 1, 0) < 0)
+		return -1;
+	return pfd.revents;
+}
+
+FN_TEST(poll_hostname)
+{
+	struct epoll_event ev = { .events = EPOLLPRI };
+	int fd, epfd;
+
+	fd = TEST_SUCC(open("/proc/sys/kernel/hostname", O_RDONLY));
+	TEST_RES(poll_pri(fd), _ret == 0);
+
+	// The change is reported once.
+	TEST_SUCC(sethostname("asterinas-poll", 14));
+	TEST_RES(poll_pri(fd), _ret == (POLLPRI | POLLERR));
+	TEST_RES(poll_pri(fd), _ret == 0);
+
+	epfd = TEST_SUCC(epoll_create1(0));
+	TEST_SUCC(epoll_ctl(epfd, EPOLL_CTL_ADD, fd, &ev));
+	TEST_RES(epoll_wait(epfd, &ev, 1, 0), _ret == 0);
+
+	TEST_SUCC(sethostname(old_name.nodename, strlen(old_name.nodename)));
+	TEST_RES(epoll_wait(epfd, &ev, 1, 0),
+		 _ret == 1 && ev.events == (EPOLLPRI | EPOLLERR));
+	TEST_RES(epoll_wait(epfd, &ev, 1, 0), _ret == 0);
+
+	// The domain name is not changed.
+	TEST_SUCC(setdomainname(old_name.domainname,
+				strlen(old_name.domainname)));
+	TEST_RES(epoll_wait(epfd, &ev, 1, 0), _ret == 0);
+
+	TEST_SUCC(close(epfd));
+	TEST_SUCC(close(fd));
+}
+END_TEST()
@@ -0,0 +1,237 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <signal.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/mman.h>
+#include <sys/resource.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+
+#define EXIT_CODE 3
+
+static volatile sig_atomic_t nr_sigchld;
+
+static void count_sigchld(int signum)
+{
+	nr_sigchld++;
+}
+
+static void catch_sigchld(int flags)
+{
+	struct sigaction action = { .sa_handler = count_sigchld,
+				    .sa_flags = SA_RESTART | flags };
+
+	CHECK(sigaction(SIGCHLD, &action, NULL));
+	nr_sigchld = 0;
+}
+
+static pid_t fork_stopping_child(void)
+{
+	pid_t pid = CHECK(fork());
+
+	// The child waits to be killed after it is continued
+	if (pid == 0) {
+		raise(SIGSTOP);
+		pause();
+		_exit(EXIT_CODE);
+	}
+
+	return pid;
+}
+
+FN_TEST(stopped_and_continued)
+{
+	pid_t pid;
+	int status;
+
+	catch_sigchld(0);
+	pid = TEST_SUCC(fork_stopping_child());
+
+	// The stop is reported once
+	TEST_RES(waitpid(pid, &status, WUNTRACED),
+		 _ret == pid && WIFSTOPPED(status) &&
+			 WSTOPSIG(status) == SIGSTOP && nr_sigchld == 1);
+	TEST_RES(waitpid(pid, &status, WUNTRACED | WNOHANG), _ret == 0);
+
+	// The continue is reported once
+	TEST_SUCC(kill(pid, SIGCONT));
+	TEST_RES(waitpid(pid, &status, WCONTINUED),
+		 _ret == pid && WIFCONTINUED(status));
+	TEST_RES(waitpid(pid, &status, WCONTINUED | WNOHANG), _ret == 0);
+
+	TEST_SUCC(kill(pid, SIGKILL));
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSIGNALED(status) &&
+			 WTERMSIG(status) == SIGKILL && nr_sigchld >= 2);
+
+	signal(SIGCHLD, SIG_DFL);
+}
+END_TEST()
+
+FN_TEST(nocldstop)
+{
+	pid_t pid;
+	int status;
+
+	// No `SIGCHLD` is sent for the stop and the continue
+	catch_sigchld(SA_NOCLDSTOP);
+	pid = TEST_SUCC(fork_stopping_child());
+
+	TEST_RES(waitpid(pid, &status, WUNTRACED),
+		 _ret == pid && WIFSTOPPED(status) && nr_sigchld == 0);
+	TEST_SUCC(kill(pid, SIGCONT));
+	TEST_SUCC(kill(pid, SIGKILL));
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSIGNALED(status) && nr_sigchld == 1);
+
+	signal(SIGCHLD, SIG_DFL);
+}
+END_TEST()
+
+FN_TEST(waitid_exited)
+{
+	siginfo_t info;
+	pid_t pid;
+
+	pid = CHECK(fork());
+	if (pid == 0)
+		_exit(EXIT_CODE);
+
+	// `WNOWAIT` leaves the child in the waitable state
+	memset(&info, 0xff, sizeof(info));
+	TEST_RES(waitid(P_PID, pid, &info, WEXITED | WNOWAIT),
+		 _ret == 0 && info.si_signo == SIGCHLD && info.si_pid == pid &&
+			 info.si_uid == getuid() &&
+			 info.si_code == CLD_EXITED &&
+			 info.si_status == EXIT_CODE);
+
+	memset(&info, 0xff, sizeof(info));
+	TEST_RES(waitid(P_ALL, 0, &info, WEXITED),
+		 _ret == 0 && info.si_pid == pid &&
+			 info.si_code == CLD_EXITED &&
+			 info.si_status == EXIT_CODE);
+	TEST_ERRNO(waitid(P_PID, pid, &info, WEXITED), ECHILD);
+}
+END_TEST()
+
+FN_TEST(waitid_killed)
+{
+	siginfo_t info;
+	pid_t pid;
+
+	pid = CHECK(fork());
+	if (pid == 0) {
+		pause();
+		_exit(EXIT_CODE);
+	}
+
+	// No child has changed its status
+	memset(&info, 0xff, sizeof(info));
+	TEST_RES(waitid(P_PID, pid, &info, WEXITED | WNOHANG),
+		 _ret == 0 && info.si_pid == 0);
+
+	TEST_SUCC(kill(pid, SIGTERM));
+	TEST_RES(waitid(P_PID, pid, &info, WEXITED),
+		 _ret == 0 && info.si_pid == pid &&
+			 info.si_code == CLD_KILLED &&
+			 info.si_status == SIGTERM);
+}
+END_TEST()
+
+FN_TEST(waitid_stopped_and_continued)
+{
+	siginfo_t info;
+	pid_t pid;
+
+	pid = TEST_SUCC(fork_stopping_child());
+
+	TEST_RES(waitid(P_PID, pid, &info, WSTOPPED),
+		 _ret == 0 && info.si_pid == pid &&
+			 info.si_code == CLD_STOPPED &&
+			 info.si_status == SIGSTOP);
+
+	TEST_SUCC(kill(pid, SIGCONT));
+	TEST_RES(waitid(P_PID, pid, &info, WCONTINUED),
+		 _ret == 0 && info.si_pid == pid &&
+			 info.si_code == CLD_CONTINUED &&
+			 info.si_status == SIGCONT);
+	memset(&info, 0xff, sizeof(info));
+	TEST_RES(waitid(P_PID, pid, &info, WCONTINUED | WNOHANG),
+		 _ret == 0 && info.si_pid == 0);
+
+	TEST_SUCC(kill(pid, SIGKILL));
+	TEST_RES(waitid(P_PID, pid, &info, WEXITED),
+		 _ret == 0 && info.si_code == CLD_KILLED);
+}
+END_TEST()
+
+FN_TEST(waitid_pgid)
+{
+	siginfo_t info;
+	pid_t pid;
+
+	pid = CHECK(fork());
+	if (pid == 0) {
+		CHECK(setpgid(0, 0));
+		pause();
+		_exit(EXIT_CODE);
+	}
+	// Avoid racing with the child
+	TEST_SUCC(setpgid(pid, pid));
+
+	TEST_ERRNO(waitid(P_PGID, getpgrp(), &info, WEXITED | WNOHANG),
+		   ECHILD);
+
+	TEST_SUCC(kill(pid, SIGKILL));
+	TEST_RES(waitid(P_PGID, pid, &info, WEXITED),
+		 _ret == 0 && info.si_pid == pid &&
+			 info.si_code == CLD_KILLED &&
+			 info.si_status == SIGKILL);
+}
+END_TEST()
+
+FN_TEST(invalid_options)
+{
+	siginfo_t info;
+	int status;
+
+	// `waitid` must wait for some status changes
+	TEST_ERRNO(waitid(P_ALL, 0, &info, WNOHANG), EINVAL);
+
+	// `WEXITED` and `WNOWAIT` are only for `waitid`
+	TEST_ERRNO(syscall(SYS_wait4, -1, &status, WEXITED, NULL), EINVAL);
+	TEST_ERRNO(syscall(SYS_wait4, -1, &status, WNOWAIT, NULL), EINVAL);
+}
+END_TEST()
+
+#define TOUCHED_SIZE (16 * 1024 * 1024)
+
+FN_TEST(rusage)
+{
+	struct rusage rusage;
+	pid_t pid;
+	int status;
+
+	pid = CHECK(fork());
+	if (pid == 0) {
+		char *buf = mmap(NULL, TOUCHED_SIZE, PROT_READ | PROT_WRITE,
+				 MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+		if (buf == MAP_FAILED)
+			_exit(EXIT_FAILURE);
+		memset(buf, 1, TOUCHED_SIZE);
+		// Make the peak in the middle of its life
+		CHECK(munmap(buf, TOUCHED_SIZE / 2));
+		_exit(EXIT_CODE);
+	}
+
+	TEST_RES(wait4(pid, &status, 0, &rusage),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_CODE &&
+			 rusage.ru_maxrss >= TOUCHED_SIZE / 2 / 1024);
+}
+END_TEST()
@@ -0,0 +1,209 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <linux/futex.h>
+#include <pthread.h>
+#include <stdint.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+static long futex(uint32_t *uaddr, int op, uint32_t val, unsigned long val2,
+		  uint32_t *uaddr2, uint32_t val3)
+{
+	return syscall(SYS_futex, uaddr, op, val, val2, uaddr2, val3);
+}
+
+static uint32_t futex_word;
+static uint32_t futex_word2;
+
+static void *wait_futex(void *arg)
+{
+	futex((uint32_t *)arg, FUTEX_WAIT_PRIVATE, 0, 0, NULL, 0);
+	return NULL;
+}
+
+static void spawn_waiters(pthread_t *threads, int nr_threads, uint32_t *addr)
+{
+	for (int i = 0; i < nr_threads; ++i)
+		CHECK(pthread_create(&threads[i], NULL, wait_futex, addr));
+	// Give the threads a chance to start waiting.
+	usleep(100 * 1000);
+}
+
+FN_TEST(cmp_requeue)
+{
+	pthread_t threads[3];
+
+	futex_word = 0;
+	futex_word2 = 0;
+	spawn_waiters(threads, 3, &futex_word);
+
+	TEST_ERRNO(futex(&futex_word, FUTEX_CMP_REQUEUE_PRIVATE, 1, 1,
+			 &futex_word2, 1),
+		   EAGAIN);
+
+	// One waiter is woken up and the other two are requeued.
+	TEST_RES(futex(&futex_word, FUTEX_CMP_REQUEUE_PRIVATE, 1, 2,
+		       &futex_word2, 0),
+		 _ret == 3);
+	TEST_RES(futex(&futex_word, FUTEX_WAKE_PRIVATE, 2, 0, NULL, 0),
+		 _ret == 0);
+	TEST_RES(futex(&futex_word2, FUTEX_WAKE_PRIVATE, 2, 0, NULL, 0),
+		 _ret == 2);
+
+	for (int i = 0; i < 3; ++i)
+		TEST_SUCC(pthread_join(threads[i], NULL));
+}
+END_TEST()
+
+FN_TEST(wake_op)
+{
+	pthread_t threads[2];
+
+	futex_word = 0;
+	futex_word2 = 0;
+	spawn_waiters(threads, 1, &futex_word);
+	spawn_waiters(threads + 1, 1, &futex_word2);
+
+	// The old value of `futex_word2` is zero, so only the waiter on `futex_word` is woken up.
+	TEST_RES(futex(&futex_word, FUTEX_WAKE_OP_PRIVATE, 1, 1, &futex_word2,
+		       FUTEX_OP(FUTEX_OP_ADD, 1, FUTEX_OP_CMP_NE, 0)),
+		 _ret == 1 && futex_word2 == 1);
+
+	// The old value of `futex_word2` is one, so the waiter on `futex_word2` is woken up.
+	TEST_RES(futex(&futex_word, FUTEX_WAKE_OP_PRIVATE, 1, 1, &futex_word2,
+		       FUTEX_OP(FUTEX_OP_SET, 0, FUTEX_OP_CMP_EQ, 1)),
+		 _ret == 1 && futex_word2 == 0);
+
+	TEST_ERRNO(futex(&futex_word, FUTEX_WAKE_OP_PRIVATE, 1, 1, &futex_word2,
+			 0x7 << 28),
+		   ENOSYS);
+
+	for (int i = 0; i < 2; ++i)
+		TEST_SUCC(pthread_join(threads[i], NULL));
+}
+END_TEST()
+
+static void *lock_pi_futex(void *arg)
+{
+	uint32_t *addr = arg;
+
+	if (futex(addr, FUTEX_LOCK_PI_PRIVATE, 0, 0, NULL, 0) < 0)
+		return (void *)1;
+	if ((*addr & FUTEX_TID_MASK) != gettid())
+		return (void *)2;
+	if (futex(addr, FUTEX_UNLOCK_PI_PRIVATE, 0, 0, NULL, 0) < 0)
+		return (void *)3;
+	return NULL;
+}
+
+FN_TEST(lock_pi)
+{
+	pthread_t thread;
+	void *retval;
+
+	futex_word = 0;
+
+	TEST_RES(futex(&futex_word, FUTEX_LOCK_PI_PRIVATE, 0, 0, NULL, 0),
+		 _ret == 0 && futex_word == gettid());
+	TEST_ERRNO(futex(&futex_word, FUTEX_LOCK_PI_PRIVATE, 0, 0, NULL, 0),
+		   EDEADLK);
+
+	TEST_SUCC(pthread_create(&thread, NULL, lock_pi_futex, &futex_word));
+	usleep(100 * 1000);
+	TEST_RES(futex_word, _ret == (gettid() | FUTEX_WAITERS));
+
+	TEST_SUCC(futex(&futex_word, FUTEX_UNLOCK_PI_PRIVATE, 0, 0, NULL, 0));
+	TEST_RES(pthread_join(thread, &retval), _ret == 0 && retval == NULL);
+	TEST_RES(futex_word, _ret == 0);
+
+	TEST_ERRNO(futex(&futex_word, FUTEX_UNLOCK_PI_PRIVATE, 0, 0, NULL, 0),
+		   EPERM);
+}
+END_TEST()
+
+FN_TEST(trylock_pi)
+{
+	futex_word = 0;
+
+	TEST_RES(futex(&futex_word, FUTEX_TRYLOCK_PI_PRIVATE, 0, 0, NULL, 0),
+		 _ret == 0 && futex_word == gettid());
+	TEST_SUCC(futex(&futex_word, FUTEX_UNLOCK_PI_PRIVATE, 0, 0, NULL, 0));
+
+	// The owner does not exist.
+	futex_word = 0x3FFFFFF0;
+	TEST_ERRNO(futex(&futex_word, FUTEX_TRYLOCK_PI_PRIVATE, 0, 0, NULL, 0),
+		   ESRCH);
+}
+END_TEST()
+
+FN_TEST(get_robust_list)
+{
+	struct robust_list_head *head;
+	size_t len;
+
+	TEST_SUCC(syscall(SYS_get_robust_list, 0, &head, &len));
+	TEST_RES(len, _ret == sizeof(struct robust_list_head));
+
+	// glibc registers the robust list of each thread at its start.
+	TEST_SUCC(syscall(SYS_set_robust_list, head, len));
+	TEST_SUCC(syscall(SYS_get_robust_list, gettid(), &head, &len));
+
+	TEST_ERRNO(syscall(SYS_set_robust_list, head, len + 1), EINVAL);
+	TEST_ERRNO(syscall(SYS_get_robust_list, 0x3FFFFFF0, &head, &len),
+		   ESRCH);
+}
+END_TEST()
+
+static pthread_mutex_t robust_mutex;
+
+static void *lock_and_exit(void *arg)
+{
+	pthread_mutex_lock(&robust_mutex);
+	return NULL;
+}
+
+static void init_robust_mutex(int protocol)
+{
+	pthread_mutexattr_t attr;
+
+	CHECK(pthread_mutexattr_init(&attr));
+	CHECK(pthread_mutexattr_setrobust(&attr, PTHREAD_MUTEX_ROBUST));
+	CHECK(pthread_mutexattr_setprotocol(&attr, protocol));
+	CHECK(pthread_mutex_init(&robust_mutex, &attr));
+	CHECK(pthread_mutexattr_destroy(&attr));
+}
+
+#define TEST_OWNER_DEAD()                                                   \
+	do {                                                                \
+		pthread_t thread;                                           \
+                                                                            \
+		TEST_SUCC(pthread_create(&thread, NULL, lock_and_exit, NULL)); \
+		TEST_SUCC(pthread_join(thread, NULL));                      \
+                                                                            \
+		TEST_RES(pthread_mutex_lock(&robust_mutex), _ret == EOWNERDEAD); \
+		TEST_SUCC(pthread_mutex_consistent(&robust_mutex));         \
+		TEST_SUCC(pthread_mutex_unlock(&robust_mutex));             \
+                                                                            \
+		TEST_SUCC(pthread_mutex_lock(&robust_mutex));               \
+		TEST_SUCC(pthread_mutex_unlock(&robust_mutex));             \
+                                                                            \
+		TEST_SUCC(pthread_mutex_destroy(&robust_mutex));            \
+	} while (0)
+
+FN_TEST(robust_mutex)
+{
+	init_robust_mutex(PTHREAD_PRIO_NONE);
+	TEST_OWNER_DEAD();
+}
+END_TEST()
+
+FN_TEST(robust_pi_mutex)
+{
+	init_robust_mutex(PTHREAD_PRIO_INHERIT);
+	TEST_OWNER_DEAD();
+}
+END_TEST()
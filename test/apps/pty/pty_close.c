@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <poll.h>
+#include <stdlib.h>
+#include <sys/ioctl.h>
+#include <unistd.h>
+
+static int master, slave;
+static char slave_name[64];
+
+FN_SETUP(open_master)
+{
+	master = CHECK(posix_openpt(O_RDWR | O_NOCTTY));
+	CHECK(ptsname_r(master, slave_name, sizeof(slave_name)));
+}
+END_SETUP()
+
+FN_TEST(lock)
+{
+	int is_locked;
+
+	TEST_RES(ioctl(master, TIOCGPTLCK, &is_locked), is_locked == 1);
+	TEST_ERRNO(open(slave_name, O_RDWR | O_NOCTTY), EIO);
+
+	TEST_SUCC(unlockpt(master));
+	TEST_RES(ioctl(master, TIOCGPTLCK, &is_locked), is_locked == 0);
+	slave = TEST_SUCC(open(slave_name, O_RDWR | O_NOCTTY));
+}
+END_TEST()
+
+FN_TEST(close_slave)
+{
+	struct pollfd pfd = { .fd = master, .events = POLLIN };
+	char buf[16];
+
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+
+	TEST_SUCC(close(slave));
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && (pfd.revents & POLLHUP));
+	TEST_ERRNO(read(master, buf, sizeof(buf)), EIO);
+
+	slave = TEST_SUCC(open(slave_name, O_RDWR | O_NOCTTY));
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(close_master)
+{
+	struct pollfd pfd = { .fd = slave, .events = POLLIN };
+	char buf[16];
+
+	TEST_SUCC(close(master));
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && (pfd.revents & POLLHUP));
+	TEST_RES(read(slave, buf, sizeof(buf)), _ret == 0);
+	TEST_ERRNO(write(slave, "a", 1), EIO);
+
+	TEST_SUCC(close(slave));
+}
+END_TEST()
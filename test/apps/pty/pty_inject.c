@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <pty.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <sys/wait.h>
+#include <termios.h>
+#include <unistd.h>
+
+static int master, slave;
+
+FN_SETUP(openpty)
+{
+	struct termios term;
+
+	CHECK(openpty(&master, &slave, NULL, NULL, NULL));
+
+	// Disable echoing so that the master sees the injected bytes only
+	CHECK(tcgetattr(slave, &term));
+	term.c_lflag &= ~ECHO;
+	CHECK(tcsetattr(slave, TCSANOW, &term));
+}
+END_SETUP()
+
+static int inject(int fd, const char *input)
+{
+	for (; *input; ++input)
+		if (ioctl(fd, TIOCSTI, input) < 0)
+			return -1;
+	return 0;
+}
+
+FN_TEST(inject_privileged)
+{
+	char buf[8];
+
+	// The terminal is not our controlling terminal, but we're privileged
+	TEST_SUCC(inject(slave, "ab\n"));
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 3 && memcmp(buf, "ab\n", 3) == 0);
+
+	// Injecting via the master makes the bytes readable from the master
+	TEST_SUCC(inject(master, "c"));
+	TEST_RES(read(master, buf, sizeof(buf)), _ret == 1 && buf[0] == 'c');
+
+	TEST_ERRNO(ioctl(slave, TIOCSTI, NULL), EFAULT);
+}
+END_TEST()
+
+FN_TEST(inject_unprivileged)
+{
+	char buf[8];
+	int status;
+	pid_t pid;
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		// Switching to a non-root user drops all the capabilities
+		CHECK(setuid(65534));
+
+		// The terminal is not our controlling terminal
+		CHECK_WITH(inject(slave, "a"), _ret < 0 && errno == EPERM);
+		CHECK_WITH(inject(master, "a"), _ret < 0 && errno == EPERM);
+
+		// The terminal is our controlling terminal
+		CHECK(setsid());
+		CHECK(ioctl(slave, TIOCSCTTY, 0));
+		CHECK(inject(slave, "d\n"));
+		CHECK_WITH(read(slave, buf, sizeof(buf)),
+			   _ret == 2 && memcmp(buf, "d\n", 2) == 0);
+
+		exit(EXIT_SUCCESS);
+	}
+
+	TEST_RES(wait4(pid, &status, 0, NULL),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+}
+END_TEST()
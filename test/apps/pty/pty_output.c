@@ -0,0 +1,110 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <pty.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <termios.h>
+#include <unistd.h>
+
+static int master, slave;
+
+FN_SETUP(openpty)
+{
+	CHECK(openpty(&master, &slave, NULL, NULL, NULL));
+}
+END_SETUP()
+
+static int set_oflag(tcflag_t oflag)
+{
+	struct termios term;
+
+	if (tcgetattr(slave, &term) < 0)
+		return -1;
+	term.c_oflag = oflag;
+	return tcsetattr(slave, TCSADRAIN, &term);
+}
+
+static int write_and_check(const char *input, const char *output)
+{
+	char buf[64];
+	ssize_t len;
+
+	if (write(slave, input, strlen(input)) != strlen(input))
+		return -1;
+	len = read(master, buf, sizeof(buf));
+	if (len < 0)
+		return -1;
+
+	return len == strlen(output) && memcmp(buf, output, len) == 0;
+}
+
+FN_TEST(onlcr)
+{
+	TEST_SUCC(set_oflag(OPOST | ONLCR));
+	TEST_RES(write_and_check("a\nb\n", "a\r\nb\r\n"), _ret == 1);
+
+	TEST_SUCC(set_oflag(ONLCR));
+	TEST_RES(write_and_check("a\nb\n", "a\nb\n"), _ret == 1);
+}
+END_TEST()
+
+FN_TEST(ocrnl_and_onocr)
+{
+	TEST_SUCC(set_oflag(OPOST | OCRNL));
+	TEST_RES(write_and_check("a\r", "a\n"), _ret == 1);
+
+	// The cursor is moved to the first column by `\n`, so `\r` is dropped.
+	TEST_SUCC(set_oflag(OPOST | ONOCR | ONLRET));
+	TEST_RES(write_and_check("a\n\rb\r", "a\nb\r"), _ret == 1);
+}
+END_TEST()
+
+FN_TEST(xtabs)
+{
+	TEST_SUCC(set_oflag(OPOST | XTABS));
+	TEST_RES(write_and_check("ab\tc\r\t", "ab      c\r        "), _ret == 1);
+}
+END_TEST()
+
+FN_TEST(drain_and_flush)
+{
+	TEST_SUCC(tcdrain(slave));
+	TEST_SUCC(tcflush(slave, TCIOFLUSH));
+	TEST_ERRNO(tcflush(slave, 3), EINVAL);
+	TEST_ERRNO(tcflow(slave, 4), EINVAL);
+}
+END_TEST()
+
+FN_TEST(queue_lengths)
+{
+	struct termios term;
+	char buf[8];
+	int len;
+
+	TEST_SUCC(tcgetattr(slave, &term));
+	term.c_lflag |= ICANON;
+	term.c_lflag &= ~ECHO;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+
+	// Only the complete lines are counted in canonical mode.
+	TEST_RES(write(master, "ab", 2), _ret == 2);
+	TEST_RES(ioctl(slave, TIOCINQ, &len), _ret == 0 && len == 0);
+	TEST_RES(write(master, "\n", 1), _ret == 1);
+	TEST_RES(ioctl(slave, TIOCINQ, &len), _ret == 0 && len == 3);
+	TEST_RES(ioctl(slave, FIONREAD, &len), _ret == 0 && len == 3);
+	TEST_RES(read(slave, buf, sizeof(buf)), _ret == 3);
+	TEST_RES(ioctl(slave, TIOCINQ, &len), _ret == 0 && len == 0);
+
+	// The output is sent to the master immediately.
+	TEST_RES(write(slave, "xyz", 3), _ret == 3);
+	TEST_RES(ioctl(slave, TIOCOUTQ, &len), _ret == 0 && len == 0);
+	TEST_RES(ioctl(master, TIOCOUTQ, &len), _ret == 0 && len == 0);
+	TEST_RES(ioctl(master, TIOCINQ, &len), _ret == 0 && len == 3);
+	TEST_RES(read(master, buf, sizeof(buf)), _ret == 3);
+}
+END_TEST()
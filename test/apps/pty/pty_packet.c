@@ -0,0 +1,223 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <poll.h>
+#include <pty.h>
+#include <signal.h>
+#include <string.h>
+#include <sys/epoll.h>
+#include <sys/ioctl.h>
+#include <sys/select.h>
+#include <termios.h>
+#include <unistd.h>
+
+static int master, slave;
+static struct termios orig_term;
+
+FN_SETUP(openpty)
+{
+	int enabled = 1;
+
+	CHECK(openpty(&master, &slave, NULL, NULL, NULL));
+	CHECK(tcgetattr(slave, &orig_term));
+	CHECK(ioctl(master, TIOCPKT, &enabled));
+}
+END_SETUP()
+
+static int read_status(void)
+{
+	unsigned char buf[8];
+	struct pollfd pfd;
+
+	pfd.fd = master;
+	pfd.events = POLLIN | POLLPRI;
+	if (poll(&pfd, 1, 0) != 1 || pfd.revents != (POLLIN | POLLPRI))
+		return -1;
+
+	// The status is reported in a packet of its own.
+	if (read(master, buf, sizeof(buf)) != 1)
+		return -1;
+
+	return buf[0];
+}
+
+static int no_status(void)
+{
+	struct pollfd pfd;
+
+	pfd.fd = master;
+	pfd.events = POLLIN | POLLPRI;
+	return poll(&pfd, 1, 0) == 0;
+}
+
+FN_TEST(packet_mode)
+{
+	int enabled;
+
+	TEST_RES(ioctl(master, TIOCGPKT, &enabled), enabled == 1);
+	TEST_RES(no_status(), _ret == 1);
+}
+END_TEST()
+
+FN_TEST(data_packet)
+{
+	char buf[8];
+
+	TEST_RES(write(slave, "ab", 2), _ret == 2);
+	TEST_RES(read(master, buf, sizeof(buf)),
+		 _ret == 3 && buf[0] == TIOCPKT_DATA &&
+			 memcmp(buf + 1, "ab", 2) == 0);
+}
+END_TEST()
+
+FN_TEST(flush_status)
+{
+	TEST_SUCC(tcflush(slave, TCIFLUSH));
+	TEST_RES(read_status(), _ret == TIOCPKT_FLUSHREAD);
+
+	TEST_SUCC(tcflush(slave, TCIOFLUSH));
+	TEST_RES(read_status(),
+		 _ret == (TIOCPKT_FLUSHREAD | TIOCPKT_FLUSHWRITE));
+	TEST_RES(no_status(), _ret == 1);
+}
+END_TEST()
+
+static int has_exception(int fd)
+{
+	struct timeval tv = { 0, 0 };
+	fd_set exceptfds;
+
+	FD_ZERO(&exceptfds);
+	FD_SET(fd, &exceptfds);
+	return select(fd + 1, NULL, NULL, &exceptfds, &tv) == 1 &&
+	       FD_ISSET(fd, &exceptfds);
+}
+
+FN_TEST(exceptional_condition)
+{
+	struct epoll_event ev = { .events = EPOLLPRI };
+	struct pollfd pfd;
+	int epfd;
+
+	epfd = TEST_SUCC(epoll_create1(0));
+	TEST_SUCC(epoll_ctl(epfd, EPOLL_CTL_ADD, master, &ev));
+	TEST_RES(epoll_wait(epfd, &ev, 1, 0), _ret == 0);
+
+	TEST_SUCC(tcflush(slave, TCIFLUSH));
+
+	// The pending status is reported even if only `POLLPRI` is requested.
+	pfd.fd = master;
+	pfd.events = POLLPRI;
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && pfd.revents == POLLPRI);
+	TEST_RES(epoll_wait(epfd, &ev, 1, 0),
+		 _ret == 1 && ev.events == EPOLLPRI);
+	TEST_RES(has_exception(master), _ret);
+
+	TEST_RES(read_status(), _ret == TIOCPKT_FLUSHREAD);
+	TEST_RES(epoll_wait(epfd, &ev, 1, 0), _ret == 0);
+
+	TEST_SUCC(close(epfd));
+}
+END_TEST()
+
+FN_TEST(flow_status)
+{
+	TEST_SUCC(tcflow(slave, TCOOFF));
+	TEST_RES(read_status(), _ret == TIOCPKT_STOP);
+	TEST_SUCC(tcflow(slave, TCOON));
+	TEST_RES(read_status(), _ret == TIOCPKT_START);
+
+	// The status is reported only if the output is actually stopped or
+	// restarted.
+	TEST_SUCC(tcflow(slave, TCOON));
+	TEST_RES(no_status(), _ret == 1);
+
+	// ^S and ^Q typed on the master also stop and restart the output.
+	TEST_RES(write(master, "\x13", 1), _ret == 1);
+	TEST_RES(read_status(), _ret == TIOCPKT_STOP);
+	TEST_RES(write(master, "\x11", 1), _ret == 1);
+	TEST_RES(read_status(), _ret == TIOCPKT_START);
+}
+END_TEST()
+
+FN_TEST(stop_status)
+{
+	struct termios term = orig_term;
+
+	term.c_iflag &= ~IXON;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+	TEST_RES(read_status(), _ret == TIOCPKT_NOSTOP);
+
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &orig_term));
+	TEST_RES(read_status(), _ret == TIOCPKT_DOSTOP);
+}
+END_TEST()
+
+FN_TEST(extproc)
+{
+	struct termios term = orig_term;
+	char buf[8];
+
+	term.c_lflag |= EXTPROC;
+	term.c_lflag &= ~ECHO;
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &term));
+	TEST_RES(read_status(), _ret == TIOCPKT_IOCTL);
+
+	// The editing characters are not handled by the line discipline.
+	TEST_RES(write(master, "a\177b\n", 4), _ret == 4);
+	TEST_RES(read(slave, buf, sizeof(buf)),
+		 _ret == 4 && memcmp(buf, "a\177b\n", 4) == 0);
+
+	TEST_SUCC(tcsetattr(slave, TCSANOW, &orig_term));
+	TEST_RES(read_status(), _ret == TIOCPKT_IOCTL);
+}
+END_TEST()
+
+FN_TEST(send_signal)
+{
+	TEST_ERRNO(ioctl(master, TIOCSIG, SIGKILL), EINVAL);
+	TEST_ERRNO(ioctl(master, TIOCSIG, 0), EINVAL);
+	// There is no foreground process group, so the signal is dropped.
+	TEST_SUCC(ioctl(master, TIOCSIG, SIGINT));
+}
+END_TEST()
+
+FN_TEST(disable_packet_mode)
+{
+	int enabled = 0;
+	char buf[8];
+
+	TEST_SUCC(tcflush(slave, TCIFLUSH));
+	TEST_SUCC(ioctl(master, TIOCPKT, &enabled));
+	TEST_RES(ioctl(master, TIOCGPKT, &enabled), enabled == 0);
+
+	// The pending status is discarded, and the data are no longer
+	// prefixed.
+	TEST_RES(write(slave, "ab", 2), _ret == 2);
+	TEST_RES(read(master, buf, sizeof(buf)),
+		 _ret == 2 && memcmp(buf, "ab", 2) == 0);
+}
+END_TEST()
+
+FN_TEST(slave_closed)
+{
+	struct pollfd pfd;
+
+	TEST_SUCC(close(slave));
+
+	// The hang-up is reported even if no events are requested.
+	pfd.fd = master;
+	pfd.events = 0;
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && pfd.revents == POLLHUP);
+}
+END_TEST()
+
+FN_SETUP(close)
+{
+	CHECK(close(master));
+}
+END_SETUP()
@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <stdio.h>
+#include <string.h>
+#include <time.h>
+#include <unistd.h>
+
+struct cpu_times {
+	unsigned long long user;
+	unsigned long long system;
+	unsigned long long idle;
+};
+
+// Reads the times of all CPUs from the `cpu` line and returns the number of the `cpuN` lines.
+static int read_cpu_times(struct cpu_times *times)
+{
+	char line[256];
+	int nr_cpus = 0;
+	FILE *file;
+
+	file = fopen("/proc/stat", "r");
+	if (file == NULL)
+		return -1;
+
+	while (fgets(line, sizeof(line), file) != NULL) {
+		if (strncmp(line, "cpu ", 4) == 0) {
+			if (sscanf(line, "cpu %llu %*u %llu %llu", &times->user,
+				   &times->system, &times->idle) != 3) {
+				fclose(file);
+				return -1;
+			}
+		} else if (strncmp(line, "cpu", 3) == 0) {
+			nr_cpus++;
+		}
+	}
+
+	fclose(file);
+	return nr_cpus;
+}
+
+static void spin_for_ms(long ms)
+{
+	struct timespec start, now;
+
+	clock_gettime(CLOCK_MONOTONIC, &start);
+	do {
+		clock_gettime(CLOCK_MONOTONIC, &now);
+	} while ((now.tv_sec - start.tv_sec) * 1000 +
+			 (now.tv_nsec - start.tv_nsec) / 1000000 <
+		 ms);
+}
+
+FN_TEST(cpu_lines)
+{
+	struct cpu_times times;
+
+	TEST_RES(read_cpu_times(&times),
+		 _ret == sysconf(_SC_NPROCESSORS_ONLN));
+}
+END_TEST()
+
+FN_TEST(idle_time)
+{
+	struct cpu_times before, after;
+
+	// The time is in the units of `USER_HZ`, i.e., 10 milliseconds.
+	TEST_RES(read_cpu_times(&before), _ret > 0);
+	usleep(200 * 1000);
+	TEST_RES(read_cpu_times(&after),
+		 _ret > 0 && after.idle >= before.idle + 10);
+}
+END_TEST()
+
+FN_TEST(user_time)
+{
+	struct cpu_times before, after;
+
+	TEST_RES(read_cpu_times(&before), _ret > 0);
+	spin_for_ms(200);
+	TEST_RES(read_cpu_times(&after),
+		 _ret > 0 && after.user >= before.user + 10);
+}
+END_TEST()
@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <pthread.h>
+#include <sched.h>
+#include <time.h>
+#include <unistd.h>
+
+#define NR_THREADS 2
+
+static volatile int stop;
+static volatile pid_t tids[NR_THREADS];
+static volatile int cpus[NR_THREADS];
+
+static void *spin(void *arg)
+{
+	int index = (long)arg;
+
+	tids[index] = gettid();
+	while (!stop)
+		cpus[index] = sched_getcpu();
+	return NULL;
+}
+
+FN_TEST(pull_waiting_thread)
+{
+	pthread_t threads[NR_THREADS];
+	cpu_set_t mask;
+	long i, nr_cpus = sysconf(_SC_NPROCESSORS_ONLN);
+	int round;
+
+	if (nr_cpus < 2)
+		return;
+
+	// The threads start on the same CPU, so one of them waits in the run queue.
+	CPU_ZERO(&mask);
+	CPU_SET(0, &mask);
+	TEST_SUCC(sched_setaffinity(0, sizeof(mask), &mask));
+	for (i = 0; i < NR_THREADS; i++) {
+		cpus[i] = -1;
+		TEST_RES(pthread_create(&threads[i], NULL, spin, (void *)i),
+			 _ret == 0);
+	}
+	for (i = 0; i < NR_THREADS; i++) {
+		while (tids[i] == 0)
+			sched_yield();
+	}
+
+	// After the threads are allowed to run on all CPUs, an idle CPU should take over the
+	// waiting thread.
+	CPU_ZERO(&mask);
+	for (i = 0; i < nr_cpus; i++)
+		CPU_SET(i, &mask);
+	for (i = 0; i < NR_THREADS; i++)
+		TEST_SUCC(sched_setaffinity(tids[i], sizeof(mask), &mask));
+	TEST_SUCC(sched_setaffinity(0, sizeof(mask), &mask));
+
+	for (round = 0; round < 100; round++) {
+		if (cpus[0] >= 0 && cpus[1] >= 0 && cpus[0] != cpus[1])
+			break;
+		usleep(10 * 1000);
+	}
+	TEST_RES(round, cpus[0] != cpus[1]);
+
+	stop = 1;
+	for (i = 0; i < NR_THREADS; i++)
+		TEST_RES(pthread_join(threads[i], NULL), _ret == 0);
+}
+END_TEST()
@@ -0,0 +1,224 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <stdio.h>
+#include <string.h>
+#include <time.h>
+#include <unistd.h>
+
+// Finds the line that starts with the key in `/proc/stat` and parses its value.
+static int read_stat_value(const char *key, unsigned long long *value)
+{
+	char line[256];
+	size_t len = strlen(key);
+	FILE *file;
+	int ret = -1;
+
+	file = fopen("/proc/stat", "r");
+	if (file == NULL)
+		return -1;
+
+	while (fgets(line, sizeof(line), file) != NULL) {
+		if (strncmp(line, key, len) == 0 && line[len] == ' ') {
+			if (sscanf(line + len, "%llu", value) == 1)
+				ret = 0;
+			break;
+		}
+	}
+
+	fclose(file);
+	return ret;
+}
+
+static int read_uptime(double *uptime, double *idle)
+{
+	FILE *file;
+	int ret;
+
+	file = fopen("/proc/uptime", "r");
+	if (file == NULL)
+		return -1;
+
+	ret = fscanf(file, "%lf %lf", uptime, idle) == 2 ? 0 : -1;
+	fclose(file);
+	return ret;
+}
+
+FN_TEST(uptime)
+{
+	double uptime, idle, new_uptime, new_idle;
+	struct timespec now;
+
+	TEST_SUCC(read_uptime(&uptime, &idle));
+	TEST_SUCC(clock_gettime(CLOCK_MONOTONIC, &now));
+	TEST_RES(uptime, uptime <= now.tv_sec + 1 && uptime >= now.tv_sec - 1);
+
+	usleep(200 * 1000);
+	TEST_SUCC(read_uptime(&new_uptime, &new_idle));
+	TEST_RES(new_uptime, new_uptime >= uptime + 0.1);
+	TEST_RES(new_idle, new_idle >= idle);
+}
+END_TEST()
+
+FN_TEST(boot_time)
+{
+	unsigned long long btime;
+	double uptime, idle;
+
+	TEST_SUCC(read_stat_value("btime", &btime));
+	TEST_SUCC(read_uptime(&uptime, &idle));
+	TEST_RES(btime, btime + (unsigned long long)uptime <= time(NULL) + 1 &&
+				btime + (unsigned long long)uptime + 2 >= time(NULL));
+}
+END_TEST()
+
+FN_TEST(process_counts)
+{
+	unsigned long long processes, procs_running;
+
+	TEST_SUCC(read_stat_value("processes", &processes));
+	TEST_RES(processes, processes >= (unsigned long long)getpid());
+
+	// The current thread is running.
+	TEST_SUCC(read_stat_value("procs_running", &procs_running));
+	TEST_RES(procs_running, procs_running >= 1);
+}
+END_TEST()
+
+struct loadavg {
+	double avg[3];
+	unsigned int nr_runnable;
+	unsigned int nr_threads;
+	int last_pid;
+};
+
+static int read_loadavg(struct loadavg *load)
+{
+	FILE *file;
+	int ret;
+
+	file = fopen("/proc/loadavg", "r");
+	if (file == NULL)
+		return -1;
+
+	ret = fscanf(file, "%lf %lf %lf %u/%u %d", &load->avg[0], &load->avg[1],
+		     &load->avg[2], &load->nr_runnable, &load->nr_threads,
+		     &load->last_pid);
+	fclose(file);
+	return ret == 6 ? 0 : -1;
+}
+
+FN_TEST(loadavg)
+{
+	struct loadavg load;
+
+	TEST_SUCC(read_loadavg(&load));
+	TEST_RES(load.avg[0],
+		 load.avg[0] >= 0 && load.avg[1] >= 0 && load.avg[2] >= 0);
+
+	// The current thread is runnable.
+	TEST_RES(load.nr_runnable,
+		 load.nr_runnable >= 1 && load.nr_runnable <= load.nr_threads);
+	TEST_RES(load.last_pid, load.last_pid >= getpid());
+}
+END_TEST()
+
+static int read_schedstat(unsigned long long *run_time,
+			  unsigned long long *run_delay,
+			  unsigned long long *nr_slices)
+{
+	FILE *file;
+	int ret;
+
+	file = fopen("/proc/self/schedstat", "r");
+	if (file == NULL)
+		return -1;
+
+	ret = fscanf(file, "%llu %llu %llu", run_time, run_delay, nr_slices);
+	fclose(file);
+	return ret == 3 ? 0 : -1;
+}
+
+FN_TEST(task_schedstat)
+{
+	unsigned long long run_time, run_delay, nr_slices;
+	unsigned long long new_run_time, new_run_delay, new_nr_slices;
+	struct timespec start, now;
+	long long elapsed_ns;
+
+	TEST_SUCC(read_schedstat(&run_time, &run_delay, &nr_slices));
+	TEST_RES(nr_slices, nr_slices >= 1);
+
+	// Run for 20 ms, then sleep, so that the thread is picked to run again.
+	TEST_SUCC(clock_gettime(CLOCK_MONOTONIC, &start));
+	do {
+		clock_gettime(CLOCK_MONOTONIC, &now);
+		elapsed_ns = (now.tv_sec - start.tv_sec) * 1000000000LL +
+			     (now.tv_nsec - start.tv_nsec);
+	} while (elapsed_ns < 20 * 1000 * 1000);
+	usleep(10 * 1000);
+
+	TEST_SUCC(read_schedstat(&new_run_time, &new_run_delay,
+				 &new_nr_slices));
+	TEST_RES(new_run_time, new_run_time >= run_time + 10 * 1000 * 1000);
+	TEST_RES(new_run_delay, new_run_delay >= run_delay);
+	TEST_RES(new_nr_slices, new_nr_slices > nr_slices);
+}
+END_TEST()
+
+struct cpu_schedstat {
+	unsigned long long version;
+	unsigned long long timestamp;
+	int nr_cpus;
+	unsigned long long nr_schedules;
+	unsigned long long nr_slices;
+};
+
+// Parses `/proc/schedstat` and sums up the statistics of all CPUs.
+static int read_cpu_schedstat(struct cpu_schedstat *stat)
+{
+	char line[256];
+	unsigned long long fields[9];
+	int cpu;
+	FILE *file;
+
+	memset(stat, 0, sizeof(*stat));
+
+	file = fopen("/proc/schedstat", "r");
+	if (file == NULL)
+		return -1;
+
+	while (fgets(line, sizeof(line), file) != NULL) {
+		if (sscanf(line, "version %llu", &stat->version) == 1 ||
+		    sscanf(line, "timestamp %llu", &stat->timestamp) == 1)
+			continue;
+		if (sscanf(line,
+			   "cpu%d %llu %llu %llu %llu %llu %llu %llu %llu %llu",
+			   &cpu, &fields[0], &fields[1], &fields[2], &fields[3],
+			   &fields[4], &fields[5], &fields[6], &fields[7],
+			   &fields[8]) != 10)
+			continue;
+		stat->nr_cpus++;
+		stat->nr_schedules += fields[2];
+		stat->nr_slices += fields[8];
+	}
+
+	fclose(file);
+	return 0;
+}
+
+FN_TEST(cpu_schedstat)
+{
+	struct cpu_schedstat stat;
+
+	TEST_SUCC(read_cpu_schedstat(&stat));
+	TEST_RES(stat.version, stat.version == 15);
+	TEST_RES(stat.nr_cpus, stat.nr_cpus >= 1);
+	// The idle entities are scheduled but do not run time slices.
+	TEST_RES(stat.nr_slices,
+		 stat.nr_slices >= 1 && stat.nr_schedules >= stat.nr_slices);
+}
+END_TEST()
@@ -0,0 +1,35 @@
+#!/bin/sh
+
+# SPDX-License-Identifier: MPL-2.0
+
+# Keeps updating the metadata of the Ext2 filesystem until the VM is killed.
+#
+# It's used by `make ext2_crash_test`, which kills the VM at a random time
+# and checks the consistency of the filesystem.
+
+set -e
+
+WORK_DIR=/ext2/crash_test
+
+mkdir -p ${WORK_DIR}
+cd ${WORK_DIR}
+
+echo "Ext2 crash workload started."
+
+i=0
+while true; do
+    dir=dir_$((i % 8))
+    rm -rf ${dir}
+    mkdir -p ${dir}/sub
+    for j in $(seq 1 16); do
+        dd if=/dev/urandom of=${dir}/file_${j} bs=4096 count=$((j % 5 + 1)) 2>/dev/null
+        echo "round ${i} file ${j}" >> ${dir}/log
+    done
+    ln -s ../log ${dir}/sub/link
+    mv ${dir}/file_1 ${dir}/sub/file_1
+    truncate -s 1M ${dir}/file_2
+    if [ $((i % 4)) -eq 0 ]; then
+        sync
+    fi
+    i=$((i + 1))
+done
@@ -61,7 +61,22 @@ echo "Start fdatasync test......"
 test_fdatasync
 echo "All fdatasync test passed."
 
+file_io/close_range
+file_io/rwf_flags
+file_io/io_uring
+file_io/kmsg
+file_io/syslog
+file_io/inotify
+file_io/ext2_write
+file_io/fadvise
+file_io/fcntl
+pipe/fifo
+pipe/pipe_direct
 pipe/pipe_err
 pipe/short_rw
 epoll/epoll_err
+epoll/event_fds
+epoll/epoll_flags
+epoll/epoll_nested
 epoll/poll_err
+overlayfs/ovl_ops
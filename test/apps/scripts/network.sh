@@ -31,10 +31,21 @@ sleep 0.2
 ./send_buf_full
 ./tcp_err
 ./tcp_poll
+./tcp_oob
+./tcp_backlog
 ./udp_err
+./udp_bpf
+./udp_cmsg
+./udp_busy_poll
 ./unix_err
+./unix_dgram
+./unix_scm
+./sock_cred
 
+./net_ns
 ./netlink_route
 ./rtnl_err
+./rtnl_addr
+./veth_bridge
 
 echo "All network test passed"
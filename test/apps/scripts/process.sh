@@ -10,8 +10,11 @@ cd ${SCRIPT_DIR}/..
 echo "Start process test......"
 # These test programs are sorted by name.
 tests="
+cgroup/cgroup
+clone3/clone_args_size
 clone3/clone_exit_signal
 clone3/clone_files
+clone3/clone_fs
 clone3/clone_no_exit_signal
 clone3/clone_process
 cpu_affinity/cpu_affinity
@@ -22,21 +25,62 @@ eventfd2/eventfd2
 fork/fork
 fork_c/fork
 getpid/getpid
+hello_pie/auxv
 hello_pie/hello
 hello_world/hello_world
 itimer/setitimer
 itimer/timer_create
 mmap/mmap_and_fork
+mmap/mmap_cow
 mmap/mmap_shared_filebacked
+mmap/mmap_shared_msync
 mmap/mmap_readahead
+mmap/mmap_ksm
+mmap/mmap_overcommit
+mmap/mmap_hugetlb
+process/brk
+process/checkpoint
+process/credentials
+process/exit_hangup
+process/fsgsbase
 process/group_session
+process/ioprio
 process/job_control
+process/job_control_signals
+process/kill_blocked
+process/panic_on_oops
+process/procfs_pid
+process/procfs_vmstat
+process/ptrace
+process/reboot
+process/seccomp
+process/user_ns
+process/uts_name
+process/wait
 pthread/pthread_test
+pthread/futex_ops
 pty/open_pty
+pty/pty_close
+pty/pty_inject
+pty/pty_output
+pty/pty_packet
+sched/cpu_stat
+sched/load_balance
+sched/proc_stats
 sched/sched_attr
 shm/posix_shm
+shm/sysv_shm
 signal_c/parent_death_signal
+signal_c/rt_signal
+signal_c/signal_frame
+signal_c/sigio
 signal_c/signal_test
+signal_c/thread_signal
+time/clock_settime
+time/posix_timer
+time/timens
+trace/kcov
+trace/tracefs
 "
 
 for testcase in ${tests}
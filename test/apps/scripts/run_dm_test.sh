@@ -0,0 +1,15 @@
+#!/bin/sh
+
+# SPDX-License-Identifier: MPL-2.0
+
+# To successfully run the device-mapper test, QEMU should provide a blank virtio-blk disk without a
+# serial ID (i.e., `DISK=on`), which is named `vda`.
+
+set -e
+
+DM_DIR=/test/dm
+cd ${DM_DIR}
+
+echo "Start device-mapper test......"
+./dm
+echo "DM test passed."
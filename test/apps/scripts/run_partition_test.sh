@@ -0,0 +1,15 @@
+#!/bin/sh
+
+# SPDX-License-Identifier: MPL-2.0
+
+# To successfully run the partition test, QEMU should provide a blank virtio-blk disk without a
+# serial ID (i.e., `DISK=on`), which is named `vda`.
+
+set -e
+
+PARTITION_DIR=/test/partition
+cd ${PARTITION_DIR}
+
+echo "Start partition test......"
+./partition
+echo "Partition test passed."
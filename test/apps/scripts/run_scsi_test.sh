@@ -0,0 +1,14 @@
+#!/bin/sh
+
+# SPDX-License-Identifier: MPL-2.0
+
+# To successfully run the SCSI test, QEMU should provide a virtio-SCSI disk (i.e., `SCSI=on`).
+
+set -e
+
+SCSI_DIR=/test/scsi
+cd ${SCSI_DIR}
+
+echo "Start SCSI test......"
+./sg_io
+echo "SCSI test passed."
@@ -0,0 +1,14 @@
+#!/bin/sh
+
+# SPDX-License-Identifier: MPL-2.0
+
+# To successfully run the sound test, QEMU should provide a virtio-sound device (i.e., `SOUND=on`).
+
+set -e
+
+SOUND_DIR=/test/sound
+cd ${SOUND_DIR}
+
+echo "Start sound test......"
+./pcm_playback
+echo "Sound test passed."
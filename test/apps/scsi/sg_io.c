@@ -0,0 +1,176 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+#include <fcntl.h>
+#include <scsi/sg.h>
+#include <sys/ioctl.h>
+#include <unistd.h>
+
+#define SG_PATH "/dev/sg0"
+
+// The disk is a 64 MiB null device, see `tools/qemu_args.sh`.
+#define DISK_BLOCKS (64 * 1024 * 1024 / 512)
+
+#define STATUS_CHECK_CONDITION 0x02
+#define MASKED_CHECK_CONDITION 0x01
+#define DRIVER_SENSE 0x08
+#define SENSE_KEY_ILLEGAL_REQUEST 0x05
+#define ASC_INVALID_OPCODE 0x20
+
+static int sg_fd;
+static unsigned char data[4096];
+static unsigned char sense[32];
+
+static void init_hdr(struct sg_io_hdr *hdr, unsigned char *cdb, int cdb_len,
+		     int direction, unsigned int dxfer_len)
+{
+	memset(hdr, 0, sizeof(*hdr));
+	memset(data, 0xaa, sizeof(data));
+	memset(sense, 0, sizeof(sense));
+
+	hdr->interface_id = 'S';
+	hdr->dxfer_direction = direction;
+	hdr->cmd_len = cdb_len;
+	hdr->mx_sb_len = sizeof(sense);
+	hdr->dxfer_len = dxfer_len;
+	hdr->dxferp = data;
+	hdr->cmdp = cdb;
+	hdr->sbp = sense;
+	hdr->timeout = 1000;
+}
+
+FN_SETUP(open)
+{
+	sg_fd = CHECK(open(SG_PATH, O_RDWR));
+}
+END_SETUP()
+
+FN_TEST(version)
+{
+	int version;
+
+	TEST_RES(ioctl(sg_fd, SG_GET_VERSION_NUM, &version), version >= 30000);
+	TEST_RES(ioctl(sg_fd, SG_EMULATED_HOST, &version), version == 0);
+}
+END_TEST()
+
+FN_TEST(test_unit_ready)
+{
+	struct sg_io_hdr hdr;
+	unsigned char cdb[6] = { 0x00 };
+
+	// The first command may report that the device has been reset.
+	init_hdr(&hdr, cdb, sizeof(cdb), SG_DXFER_NONE, 0);
+	TEST_SUCC(ioctl(sg_fd, SG_IO, &hdr));
+
+	init_hdr(&hdr, cdb, sizeof(cdb), SG_DXFER_NONE, 0);
+	TEST_RES(ioctl(sg_fd, SG_IO, &hdr),
+		 hdr.status == 0 && hdr.host_status == 0 &&
+			 hdr.driver_status == 0 && hdr.sb_len_wr == 0 &&
+			 hdr.info == SG_INFO_OK);
+}
+END_TEST()
+
+FN_TEST(inquiry)
+{
+	struct sg_io_hdr hdr;
+	unsigned char cdb[6] = { 0x12, 0, 0, 0, 96, 0 };
+
+	init_hdr(&hdr, cdb, sizeof(cdb), SG_DXFER_FROM_DEV, 96);
+	TEST_RES(ioctl(sg_fd, SG_IO, &hdr),
+		 hdr.status == 0 && hdr.info == SG_INFO_OK &&
+			 hdr.resid <= 96 - 36);
+
+	// The peripheral device type is a direct access block device.
+	TEST_RES(data[0], _ret == 0x00);
+	TEST_RES(memcmp(&data[8], "QEMU    ", 8), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(read_capacity)
+{
+	struct sg_io_hdr hdr;
+	unsigned char cdb[10] = { 0x25 };
+	unsigned int last_lba, block_len;
+
+	init_hdr(&hdr, cdb, sizeof(cdb), SG_DXFER_FROM_DEV, 8);
+	TEST_RES(ioctl(sg_fd, SG_IO, &hdr), hdr.status == 0 && hdr.resid == 0);
+
+	last_lba = (data[0] << 24) | (data[1] << 16) | (data[2] << 8) | data[3];
+	block_len = (data[4] << 24) | (data[5] << 16) | (data[6] << 8) | data[7];
+	TEST_RES(last_lba, _ret == DISK_BLOCKS - 1);
+	TEST_RES(block_len, _ret == 512);
+}
+END_TEST()
+
+FN_TEST(read_and_write)
+{
+	struct sg_io_hdr hdr;
+	unsigned char read_cdb[10] = { 0x28, 0, 0, 0, 0, 1, 0, 0, 8, 0 };
+	unsigned char write_cdb[10] = { 0x2a, 0, 0, 0, 0, 1, 0, 0, 8, 0 };
+
+	init_hdr(&hdr, read_cdb, sizeof(read_cdb), SG_DXFER_FROM_DEV, 4096);
+	TEST_RES(ioctl(sg_fd, SG_IO, &hdr),
+		 hdr.status == 0 && hdr.resid == 0 && data[0] == 0 &&
+			 data[4095] == 0);
+
+	init_hdr(&hdr, write_cdb, sizeof(write_cdb), SG_DXFER_TO_DEV, 4096);
+	TEST_RES(ioctl(sg_fd, SG_IO, &hdr), hdr.status == 0 && hdr.resid == 0);
+}
+END_TEST()
+
+FN_TEST(invalid_opcode)
+{
+	struct sg_io_hdr hdr;
+	unsigned char cdb[6] = { 0xff };
+
+	init_hdr(&hdr, cdb, sizeof(cdb), SG_DXFER_NONE, 0);
+	TEST_RES(ioctl(sg_fd, SG_IO, &hdr),
+		 hdr.status == STATUS_CHECK_CONDITION &&
+			 hdr.masked_status == MASKED_CHECK_CONDITION &&
+			 (hdr.driver_status & DRIVER_SENSE) != 0 &&
+			 hdr.info == SG_INFO_CHECK && hdr.sb_len_wr >= 14);
+
+	// The sense data may be in the fixed format or the descriptor format.
+	if ((sense[0] & 0x7f) >= 0x72) {
+		TEST_RES(sense[1] & 0xf, _ret == SENSE_KEY_ILLEGAL_REQUEST);
+		TEST_RES(sense[2], _ret == ASC_INVALID_OPCODE);
+	} else {
+		TEST_RES(sense[2] & 0xf, _ret == SENSE_KEY_ILLEGAL_REQUEST);
+		TEST_RES(sense[12], _ret == ASC_INVALID_OPCODE);
+	}
+}
+END_TEST()
+
+FN_TEST(invalid_hdr)
+{
+	struct sg_io_hdr hdr;
+	unsigned char cdb[6] = { 0x00 };
+
+	init_hdr(&hdr, cdb, sizeof(cdb), SG_DXFER_NONE, 0);
+	hdr.interface_id = 'Q';
+	TEST_ERRNO(ioctl(sg_fd, SG_IO, &hdr), ENOSYS);
+
+	init_hdr(&hdr, cdb, 0, SG_DXFER_NONE, 0);
+	TEST_ERRNO(ioctl(sg_fd, SG_IO, &hdr), EMSGSIZE);
+
+	init_hdr(&hdr, cdb, sizeof(cdb), SG_DXFER_NONE, 0);
+	hdr.cmdp = NULL;
+	TEST_ERRNO(ioctl(sg_fd, SG_IO, &hdr), EMSGSIZE);
+
+	init_hdr(&hdr, cdb, sizeof(cdb), -10, 0);
+	TEST_ERRNO(ioctl(sg_fd, SG_IO, &hdr), EINVAL);
+
+	init_hdr(&hdr, cdb, sizeof(cdb), SG_DXFER_TO_DEV, sizeof(data));
+	hdr.dxferp = NULL;
+	TEST_ERRNO(ioctl(sg_fd, SG_IO, &hdr), EFAULT);
+}
+END_TEST()
+
+FN_SETUP(close)
+{
+	CHECK(close(sg_fd));
+}
+END_SETUP()
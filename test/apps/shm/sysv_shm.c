@@ -0,0 +1,266 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <string.h>
+#include <sys/ipc.h>
+#include <sys/shm.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define SHM_KEY 0x5f3759df
+#define SHM_SIZE 5000
+#define PAGE_SIZE 4096
+
+static int shmid;
+
+static long attach(const void *addr, int flags)
+{
+	return (long)shmat(shmid, addr, flags);
+}
+
+FN_SETUP(create)
+{
+	shmid = CHECK(shmget(SHM_KEY, SHM_SIZE, IPC_CREAT | IPC_EXCL | 0600));
+}
+END_SETUP()
+
+FN_TEST(invalid_args)
+{
+	TEST_ERRNO(shmget(IPC_PRIVATE, 0, IPC_CREAT | 0600), EINVAL);
+	TEST_ERRNO(shmget(SHM_KEY + 1, SHM_SIZE, 0600), ENOENT);
+
+	TEST_ERRNO((long)shmat(-1, NULL, 0), EINVAL);
+	TEST_ERRNO(attach((void *)(PAGE_SIZE + 1), 0), EINVAL);
+	TEST_ERRNO(attach(NULL, SHM_REMAP), EINVAL);
+
+	TEST_ERRNO(shmdt((void *)&shmid), EINVAL);
+	TEST_ERRNO(shmctl(-1, IPC_RMID, NULL), EINVAL);
+}
+END_TEST()
+
+FN_TEST(get_existing)
+{
+	TEST_RES(shmget(SHM_KEY, SHM_SIZE, 0600), _ret == shmid);
+	TEST_RES(shmget(SHM_KEY, 0, 0), _ret == shmid);
+	TEST_RES(shmget(SHM_KEY, SHM_SIZE, IPC_CREAT | 0600), _ret == shmid);
+
+	TEST_ERRNO(shmget(SHM_KEY, SHM_SIZE, IPC_CREAT | IPC_EXCL | 0600),
+		   EEXIST);
+	TEST_ERRNO(shmget(SHM_KEY, SHM_SIZE + 1, 0600), EINVAL);
+
+	// A private segment is always new.
+	TEST_RES(shmget(IPC_PRIVATE, SHM_SIZE, IPC_CREAT | 0600),
+		 _ret != shmid && shmctl(_ret, IPC_RMID, NULL) == 0);
+}
+END_TEST()
+
+static int get_nattch(void)
+{
+	struct shmid_ds ds;
+
+	if (shmctl(shmid, IPC_STAT, &ds) < 0)
+		return -1;
+	return ds.shm_nattch;
+}
+
+FN_TEST(stat)
+{
+	struct shmid_ds ds;
+
+	TEST_RES(shmctl(shmid, IPC_STAT, &ds),
+		 ds.shm_perm.__key == SHM_KEY && ds.shm_perm.uid == getuid() &&
+			 ds.shm_perm.cuid == getuid() &&
+			 ds.shm_perm.mode == 0600 && ds.shm_segsz == SHM_SIZE &&
+			 ds.shm_cpid == getpid() && ds.shm_nattch == 0 &&
+			 ds.shm_atime == 0 && ds.shm_ctime != 0);
+
+	TEST_RES(shmctl(shmid, SHM_STAT, &ds), _ret == shmid);
+	TEST_RES(shmctl(shmid, SHM_LOCK, NULL), _ret == 0);
+	TEST_RES(shmctl(shmid, IPC_STAT, &ds),
+		 ds.shm_perm.mode == (0600 | SHM_LOCKED));
+	TEST_RES(shmctl(shmid, SHM_UNLOCK, NULL), _ret == 0);
+	TEST_RES(shmctl(shmid, IPC_STAT, &ds), ds.shm_perm.mode == 0600);
+}
+END_TEST()
+
+FN_TEST(attach_and_detach)
+{
+	char *addr1, *addr2;
+	struct shmid_ds ds;
+
+	addr1 = (char *)TEST_SUCC(attach(NULL, 0));
+	addr2 = (char *)TEST_SUCC(attach(NULL, SHM_RDONLY));
+	TEST_RES(get_nattch(), _ret == 2);
+
+	// Both attaches share the same memory, which is initially zero.
+	TEST_RES(addr1[0] | addr1[SHM_SIZE - 1], _ret == 0);
+	strcpy(addr1, "hello");
+	TEST_RES(strcmp(addr2, "hello"), _ret == 0);
+
+	TEST_RES(shmctl(shmid, IPC_STAT, &ds),
+		 ds.shm_atime != 0 && ds.shm_lpid == getpid());
+
+	TEST_SUCC(shmdt(addr1));
+	TEST_ERRNO(shmdt(addr1), EINVAL);
+	TEST_RES(get_nattch(), _ret == 1);
+
+	// The memory is kept after all the processes detach it.
+	TEST_SUCC(shmdt(addr2));
+	TEST_RES(get_nattch(), _ret == 0);
+	addr1 = (char *)TEST_SUCC(attach(NULL, 0));
+	TEST_RES(strcmp(addr1, "hello"), _ret == 0);
+	TEST_SUCC(shmdt(addr1));
+}
+END_TEST()
+
+FN_TEST(attach_at_address)
+{
+	char *addr1, *addr2;
+
+	addr1 = (char *)TEST_SUCC(attach(NULL, 0));
+	TEST_SUCC(shmdt(addr1));
+
+	addr2 = (char *)TEST_RES(attach(addr1 + 1, SHM_RND),
+				 (char *)_ret == addr1);
+	TEST_RES(attach(addr1, SHM_REMAP), (char *)_ret == addr1);
+	TEST_RES(get_nattch(), _ret == 1);
+
+	TEST_SUCC(shmdt(addr2));
+	TEST_RES(get_nattch(), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(fork_and_exit)
+{
+	char *addr;
+	int status;
+	pid_t pid;
+
+	addr = (char *)TEST_SUCC(attach(NULL, 0));
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		// The attach is inherited.
+		CHECK_WITH(get_nattch(), _ret == 2);
+		strcpy(addr, "child");
+		exit(EXIT_SUCCESS);
+	}
+
+	TEST_RES(wait(&status), _ret == pid && WIFEXITED(status) &&
+					WEXITSTATUS(status) == 0);
+	TEST_RES(strcmp(addr, "child"), _ret == 0);
+	TEST_RES(get_nattch(), _ret == 1);
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		// The attach is dropped by `execve`.
+		execlp("true", "true", NULL);
+		exit(EXIT_FAILURE);
+	}
+
+	TEST_RES(wait(&status), _ret == pid && WIFEXITED(status) &&
+					WEXITSTATUS(status) == 0);
+	TEST_RES(get_nattch(), _ret == 1);
+
+	TEST_SUCC(shmdt(addr));
+}
+END_TEST()
+
+static void drop_privileges(void)
+{
+	struct shmid_ds ds;
+
+	CHECK(setresuid(65534, 65534, 65534));
+
+	CHECK_WITH(attach(NULL, 0), _ret < 0 && errno == EACCES);
+	CHECK_WITH(shmget(SHM_KEY, SHM_SIZE, 0600), _ret < 0 && errno == EACCES);
+	CHECK_WITH(shmctl(shmid, IPC_STAT, &ds), _ret < 0 && errno == EACCES);
+	CHECK_WITH(shmctl(shmid, IPC_RMID, NULL), _ret < 0 && errno == EPERM);
+}
+
+FN_TEST(unprivileged)
+{
+	int status;
+	pid_t pid;
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		drop_privileges();
+		exit(EXIT_SUCCESS);
+	}
+
+	TEST_RES(wait(&status), _ret == pid && WIFEXITED(status) &&
+					WEXITSTATUS(status) == 0);
+}
+END_TEST()
+
+FN_TEST(set)
+{
+	struct shmid_ds ds;
+
+	TEST_SUCC(shmctl(shmid, IPC_STAT, &ds));
+	ds.shm_perm.mode = 0644;
+	TEST_SUCC(shmctl(shmid, IPC_SET, &ds));
+	TEST_RES(shmctl(shmid, IPC_STAT, &ds), ds.shm_perm.mode == 0644);
+}
+END_TEST()
+
+FN_TEST(remove)
+{
+	struct shmid_ds ds;
+	char *addr;
+	int new_shmid;
+
+	addr = (char *)TEST_SUCC(attach(NULL, 0));
+	TEST_SUCC(shmctl(shmid, IPC_RMID, NULL));
+
+	// The segment is kept until the last process detaches it, but the key
+	// is no longer associated with it.
+	TEST_RES(shmctl(shmid, IPC_STAT, &ds),
+		 ds.shm_perm.__key == IPC_PRIVATE &&
+			 (ds.shm_perm.mode & SHM_DEST) && ds.shm_nattch == 1);
+	TEST_ERRNO(shmget(SHM_KEY, SHM_SIZE, 0600), ENOENT);
+	new_shmid = TEST_SUCC(shmget(SHM_KEY, SHM_SIZE, IPC_CREAT | 0600));
+	TEST_RES(strcmp(addr, "child"), _ret == 0);
+
+	TEST_SUCC(shmdt(addr));
+	TEST_ERRNO(shmctl(shmid, IPC_STAT, &ds), EINVAL);
+
+	TEST_SUCC(shmctl(new_shmid, IPC_RMID, NULL));
+	TEST_ERRNO(shmctl(new_shmid, IPC_STAT, &ds), EINVAL);
+}
+END_TEST()
+
+FN_TEST(remove_on_exit)
+{
+	struct shmid_ds ds;
+	int status;
+	pid_t pid;
+
+	shmid = TEST_SUCC(shmget(IPC_PRIVATE, SHM_SIZE, IPC_CREAT | 0600));
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK(attach(NULL, 0));
+		CHECK(shmctl(shmid, IPC_RMID, NULL));
+		exit(EXIT_SUCCESS);
+	}
+
+	TEST_RES(wait(&status), _ret == pid && WIFEXITED(status) &&
+					WEXITSTATUS(status) == 0);
+	TEST_ERRNO(shmctl(shmid, IPC_STAT, &ds), EINVAL);
+}
+END_TEST()
+
+FN_TEST(dev_shm)
+{
+	struct stat st;
+
+	TEST_RES(stat("/dev/shm", &st),
+		 S_ISDIR(st.st_mode) && (st.st_mode & 07777) == 01777);
+}
+END_TEST()
@@ -0,0 +1,146 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <poll.h>
+#include <signal.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define MAX_RECORDS 8
+
+static struct {
+	int signo;
+	int code;
+	int value;
+	pid_t pid;
+} records[MAX_RECORDS];
+static volatile int num_records;
+
+static void handler(int signo, siginfo_t *info, void *ucontext)
+{
+	if (num_records >= MAX_RECORDS)
+		return;
+
+	records[num_records].signo = signo;
+	records[num_records].code = info->si_code;
+	records[num_records].value = info->si_value.sival_int;
+	records[num_records].pid = info->si_pid;
+	num_records++;
+}
+
+static int block_rt_signals(int how)
+{
+	sigset_t set;
+
+	sigemptyset(&set);
+	sigaddset(&set, SIGRTMIN);
+	sigaddset(&set, SIGRTMIN + 1);
+	return sigprocmask(how, &set, NULL);
+}
+
+static int queue(int signo, int value)
+{
+	union sigval sigval = { .sival_int = value };
+
+	return sigqueue(getpid(), signo, sigval);
+}
+
+FN_SETUP(install_handler)
+{
+	struct sigaction sa;
+
+	memset(&sa, 0, sizeof(sa));
+	sa.sa_sigaction = handler;
+	sa.sa_flags = SA_SIGINFO;
+	// Avoid nested handlers, so the records are in the delivery order.
+	sigaddset(&sa.sa_mask, SIGRTMIN);
+	sigaddset(&sa.sa_mask, SIGRTMIN + 1);
+	CHECK(sigaction(SIGRTMIN, &sa, NULL));
+	CHECK(sigaction(SIGRTMIN + 1, &sa, NULL));
+	CHECK(sigaction(SIGRTMAX, &sa, NULL));
+}
+END_SETUP()
+
+FN_TEST(queue_in_order)
+{
+	sigset_t pending;
+
+	num_records = 0;
+	TEST_SUCC(block_rt_signals(SIG_BLOCK));
+
+	TEST_SUCC(queue(SIGRTMIN + 1, 1));
+	TEST_SUCC(queue(SIGRTMIN, 2));
+	TEST_SUCC(queue(SIGRTMIN, 3));
+	TEST_SUCC(queue(SIGRTMIN + 1, 4));
+
+	TEST_RES(sigpending(&pending), sigismember(&pending, SIGRTMIN) &&
+					       sigismember(&pending, SIGRTMIN + 1));
+	TEST_RES(num_records, _ret == 0);
+
+	// Lower-numbered signals are delivered first, and signals of the same
+	// number are delivered in the order they are sent.
+	TEST_SUCC(block_rt_signals(SIG_UNBLOCK));
+	TEST_RES(num_records, _ret == 4);
+	TEST_RES(records[0].value, records[0].signo == SIGRTMIN && _ret == 2);
+	TEST_RES(records[1].value, records[1].signo == SIGRTMIN && _ret == 3);
+	TEST_RES(records[2].value,
+		 records[2].signo == SIGRTMIN + 1 && _ret == 1);
+	TEST_RES(records[3].value,
+		 records[3].signo == SIGRTMIN + 1 && _ret == 4);
+	TEST_RES(records[0].code, _ret == SI_QUEUE);
+	TEST_RES(records[0].pid, _ret == getpid());
+}
+END_TEST()
+
+FN_TEST(kill_rt_signal)
+{
+	num_records = 0;
+
+	TEST_SUCC(kill(getpid(), SIGRTMAX));
+	TEST_RES(num_records, _ret == 1);
+	TEST_RES(records[0].code,
+		 records[0].signo == SIGRTMAX && _ret == SI_USER);
+	TEST_RES(records[0].pid, _ret == getpid());
+}
+END_TEST()
+
+FN_TEST(blocked_rt_signal_does_not_interrupt)
+{
+	num_records = 0;
+	TEST_SUCC(block_rt_signals(SIG_BLOCK));
+	TEST_SUCC(queue(SIGRTMIN, 5));
+
+	TEST_RES(poll(NULL, 0, 10), _ret == 0);
+	TEST_RES(num_records, _ret == 0);
+
+	TEST_SUCC(block_rt_signals(SIG_UNBLOCK));
+	TEST_RES(num_records, _ret == 1);
+}
+END_TEST()
+
+FN_TEST(sigqueueinfo)
+{
+	siginfo_t info;
+
+	num_records = 0;
+	memset(&info, 0, sizeof(info));
+	info.si_code = SI_QUEUE;
+	info.si_value.sival_int = 6;
+	TEST_SUCC(syscall(SYS_rt_tgsigqueueinfo, getpid(), gettid(), SIGRTMIN,
+			  &info));
+	TEST_RES(num_records, _ret == 1);
+	TEST_RES(records[0].value, records[0].signo == SIGRTMIN && _ret == 6);
+
+	TEST_ERRNO(syscall(SYS_rt_tgsigqueueinfo, 0, gettid(), SIGRTMIN, &info),
+		   EINVAL);
+
+	// Codes that are not negative are reserved for the kernel and `kill`.
+	info.si_code = SI_USER;
+	TEST_ERRNO(syscall(SYS_rt_sigqueueinfo, getppid(), SIGRTMIN, &info),
+		   EPERM);
+}
+END_TEST()
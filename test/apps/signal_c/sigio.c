@@ -0,0 +1,114 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <signal.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+static volatile int num_sigio;
+static int sk[2];
+
+static void handler(int signo)
+{
+	if (signo == SIGIO)
+		num_sigio++;
+}
+
+FN_SETUP(install_handler)
+{
+	struct sigaction sa;
+
+	memset(&sa, 0, sizeof(sa));
+	sa.sa_handler = handler;
+	CHECK(sigaction(SIGIO, &sa, NULL));
+
+	CHECK(socketpair(AF_UNIX, SOCK_STREAM, 0, sk));
+}
+END_SETUP()
+
+static int send_and_recv(void)
+{
+	char buf[1];
+
+	if (write(sk[1], "x", 1) != 1)
+		return -1;
+	if (read(sk[0], buf, 1) != 1)
+		return -1;
+	return 0;
+}
+
+FN_TEST(getown_setown)
+{
+	struct f_owner_ex owner;
+
+	TEST_RES(fcntl(sk[0], F_GETOWN), _ret == 0);
+
+	TEST_SUCC(fcntl(sk[0], F_SETOWN, getpid()));
+	TEST_RES(fcntl(sk[0], F_GETOWN), _ret == getpid());
+	TEST_RES(fcntl(sk[0], F_GETOWN_EX, &owner),
+		 owner.type == F_OWNER_PID && owner.pid == getpid());
+
+	TEST_SUCC(fcntl(sk[0], F_SETOWN, -getpgrp()));
+	TEST_RES(fcntl(sk[0], F_GETOWN), _ret == -getpgrp());
+	TEST_RES(fcntl(sk[0], F_GETOWN_EX, &owner),
+		 owner.type == F_OWNER_PGRP && owner.pid == getpgrp());
+
+	owner.type = F_OWNER_TID;
+	owner.pid = gettid();
+	TEST_SUCC(fcntl(sk[0], F_SETOWN_EX, &owner));
+	TEST_RES(fcntl(sk[0], F_GETOWN), _ret == gettid());
+
+	owner.type = 3;
+	TEST_ERRNO(fcntl(sk[0], F_SETOWN_EX, &owner), EINVAL);
+	TEST_ERRNO(fcntl(sk[0], F_SETOWN, 0x7fffffff), ESRCH);
+
+	TEST_SUCC(fcntl(sk[0], F_SETOWN, 0));
+	TEST_RES(fcntl(sk[0], F_GETOWN), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(sigio)
+{
+	int flags;
+
+	num_sigio = 0;
+	TEST_SUCC(fcntl(sk[0], F_SETOWN, getpid()));
+
+	// No signals are sent without `O_ASYNC`.
+	TEST_SUCC(send_and_recv());
+	TEST_RES(num_sigio, _ret == 0);
+
+	flags = TEST_SUCC(fcntl(sk[0], F_GETFL));
+	TEST_SUCC(fcntl(sk[0], F_SETFL, flags | O_ASYNC));
+	TEST_RES(fcntl(sk[0], F_GETFL), _ret & O_ASYNC);
+
+	TEST_SUCC(send_and_recv());
+	TEST_RES(num_sigio, _ret > 0);
+
+	// The process group can be the owner.
+	num_sigio = 0;
+	TEST_SUCC(fcntl(sk[0], F_SETOWN, -getpgrp()));
+	TEST_SUCC(send_and_recv());
+	TEST_RES(num_sigio, _ret > 0);
+
+	// No signals are sent without an owner.
+	num_sigio = 0;
+	TEST_SUCC(fcntl(sk[0], F_SETOWN, 0));
+	TEST_SUCC(send_and_recv());
+	TEST_RES(num_sigio, _ret == 0);
+
+	TEST_SUCC(fcntl(sk[0], F_SETFL, flags));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk[0]));
+	CHECK(close(sk[1]));
+}
+END_SETUP()
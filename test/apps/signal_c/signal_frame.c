@@ -0,0 +1,155 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <setjmp.h>
+#include <signal.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <ucontext.h>
+#include <unistd.h>
+
+#define UC_FP_XSTATE 0x1
+#define FP_XSTATE_MAGIC1 0x46505853U
+// The index of `struct _fpx_sw_bytes` in the reserved words at the end of `struct _libc_fpstate`.
+#define FPX_SW_BYTES_INDEX 12
+
+static volatile int handler_signo;
+static volatile int handler_code;
+static volatile pid_t handler_pid;
+static volatile int has_fpstate;
+static volatile int has_xstate_magic;
+
+static void install(int signo, void (*handler)(int, siginfo_t *, void *))
+{
+	struct sigaction sa;
+
+	memset(&sa, 0, sizeof(sa));
+	sa.sa_sigaction = handler;
+	sa.sa_flags = SA_SIGINFO;
+	CHECK(sigaction(signo, &sa, NULL));
+}
+
+static void inspect_frame(int signo, siginfo_t *info, void *ucontext)
+{
+	ucontext_t *uc = ucontext;
+
+	handler_signo = info->si_signo;
+	handler_code = info->si_code;
+	handler_pid = info->si_pid;
+	has_fpstate = uc->uc_mcontext.fpregs != NULL;
+	has_xstate_magic =
+		!(uc->uc_flags & UC_FP_XSTATE) ||
+		uc->uc_mcontext.fpregs->__glibc_reserved1[FPX_SW_BYTES_INDEX] ==
+			FP_XSTATE_MAGIC1;
+}
+
+FN_TEST(frame_layout)
+{
+	install(SIGUSR1, inspect_frame);
+
+	TEST_SUCC(kill(getpid(), SIGUSR1));
+	TEST_RES(handler_signo, _ret == SIGUSR1);
+	TEST_RES(handler_code, _ret == SI_USER);
+	TEST_RES(handler_pid, _ret == getpid());
+	TEST_RES(has_fpstate, _ret == 1);
+	TEST_RES(has_xstate_magic, _ret == 1);
+}
+END_TEST()
+
+// Sends `SIGUSR1` to the current thread with `xmm0` set to `value`, and returns `xmm0` after the
+// signal handler returns.
+static unsigned int kill_with_xmm0(unsigned int value)
+{
+	unsigned int result;
+	long ret = SYS_kill;
+
+	asm volatile("movd %[value], %%xmm0\n\t"
+		     "syscall\n\t"
+		     "movd %%xmm0, %[result]"
+		     : [result] "=r"(result), "+a"(ret)
+		     : [value] "r"(value), "D"(getpid()), "S"(SIGUSR1)
+		     : "rcx", "r11", "xmm0", "memory");
+
+	return result;
+}
+
+static void clobber_xmm0(int signo, siginfo_t *info, void *ucontext)
+{
+	asm volatile("pxor %%xmm0, %%xmm0" ::: "xmm0");
+}
+
+FN_TEST(fp_state_is_restored)
+{
+	install(SIGUSR1, clobber_xmm0);
+
+	TEST_RES(kill_with_xmm0(0x12345678), _ret == 0x12345678);
+}
+END_TEST()
+
+static void modify_xmm0(int signo, siginfo_t *info, void *ucontext)
+{
+	ucontext_t *uc = ucontext;
+
+	uc->uc_mcontext.fpregs->_xmm[0].element[0] = 0xdeadbeef;
+}
+
+FN_TEST(fp_state_is_modified)
+{
+	install(SIGUSR1, modify_xmm0);
+
+	TEST_RES(kill_with_xmm0(0x12345678), _ret == 0xdeadbeef);
+}
+END_TEST()
+
+static void block_sigusr2(int signo, siginfo_t *info, void *ucontext)
+{
+	ucontext_t *uc = ucontext;
+
+	sigaddset(&uc->uc_sigmask, SIGUSR2);
+}
+
+FN_TEST(sigmask_is_modified)
+{
+	sigset_t set;
+
+	install(SIGUSR1, block_sigusr2);
+
+	TEST_SUCC(kill(getpid(), SIGUSR1));
+	TEST_RES(sigprocmask(SIG_BLOCK, NULL, &set),
+		 sigismember(&set, SIGUSR2) && !sigismember(&set, SIGUSR1));
+
+	sigemptyset(&set);
+	sigaddset(&set, SIGUSR2);
+	TEST_SUCC(sigprocmask(SIG_UNBLOCK, &set, NULL));
+}
+END_TEST()
+
+static sigjmp_buf jmp_env;
+
+static void jump_out(int signo, siginfo_t *info, void *ucontext)
+{
+	siglongjmp(jmp_env, 1);
+}
+
+FN_TEST(longjmp_out_of_handler)
+{
+	sigset_t set;
+	int jumped;
+
+	install(SIGUSR1, jump_out);
+
+	jumped = sigsetjmp(jmp_env, 1);
+	if (!jumped)
+		kill(getpid(), SIGUSR1);
+	TEST_RES(jumped, _ret == 1);
+
+	TEST_RES(sigprocmask(SIG_BLOCK, NULL, &set),
+		 !sigismember(&set, SIGUSR1));
+	// The FPU state is still usable after leaving the handler.
+	install(SIGUSR1, clobber_xmm0);
+	TEST_RES(kill_with_xmm0(0x87654321), _ret == 0x87654321);
+}
+END_TEST()
@@ -0,0 +1,190 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <pthread.h>
+#include <signal.h>
+#include <stdatomic.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static atomic_int handler_tid;
+
+static void handle_usr1(int sig)
+{
+	atomic_store(&handler_tid, gettid());
+}
+
+static atomic_int thread_tid;
+static atomic_int thread_exit;
+
+static void *thread_func(void *arg)
+{
+	sigset_t *mask = arg;
+	stack_t stack;
+
+	pthread_sigmask(SIG_SETMASK, mask, NULL);
+	atomic_store(&thread_tid, gettid());
+
+	while (!atomic_load(&thread_exit))
+		usleep(1000);
+
+	// The alternate signal stack is not inherited by the new thread.
+	if (sigaltstack(NULL, &stack) < 0 || !(stack.ss_flags & SS_DISABLE))
+		return (void *)-1;
+
+	return NULL;
+}
+
+static pthread_t thread;
+static sigset_t thread_mask;
+static sigset_t usr1_mask;
+
+FN_SETUP(spawn_thread)
+{
+	struct sigaction sa = { .sa_handler = handle_usr1 };
+	static char stack_buf[65536];
+	stack_t stack = { .ss_sp = stack_buf, .ss_size = sizeof(stack_buf) };
+
+	CHECK(sigaction(SIGUSR1, &sa, NULL));
+	CHECK(sigaltstack(&stack, NULL));
+
+	sigemptyset(&thread_mask);
+	sigemptyset(&usr1_mask);
+	sigaddset(&usr1_mask, SIGUSR1);
+
+	CHECK_WITH(pthread_create(&thread, NULL, thread_func, &thread_mask),
+		   _ret == 0);
+	while (atomic_load(&thread_tid) == 0)
+		usleep(1000);
+}
+END_SETUP()
+
+static int wait_handler(void)
+{
+	int i;
+
+	for (i = 0; i < 1000 && atomic_load(&handler_tid) == 0; ++i)
+		usleep(1000);
+
+	return atomic_exchange(&handler_tid, 0);
+}
+
+FN_TEST(process_directed)
+{
+	// The signal is blocked by the main thread only, so it must be
+	// delivered to the other thread.
+	TEST_SUCC(pthread_sigmask(SIG_BLOCK, &usr1_mask, NULL));
+	TEST_SUCC(kill(getpid(), SIGUSR1));
+	TEST_RES(wait_handler(), _ret == thread_tid);
+	TEST_SUCC(pthread_sigmask(SIG_UNBLOCK, &usr1_mask, NULL));
+}
+END_TEST()
+
+FN_TEST(thread_directed)
+{
+	sigset_t pending;
+
+	TEST_SUCC(syscall(SYS_tgkill, getpid(), thread_tid, SIGUSR1));
+	TEST_RES(wait_handler(), _ret == thread_tid);
+
+	TEST_SUCC(syscall(SYS_tkill, thread_tid, SIGUSR1));
+	TEST_RES(wait_handler(), _ret == thread_tid);
+
+	TEST_RES(pthread_kill(thread, SIGUSR1), _ret == 0);
+	TEST_RES(wait_handler(), _ret == thread_tid);
+
+	// The signal directed to the main thread stays pending until the
+	// main thread unblocks it, even if the other thread does not block it.
+	TEST_SUCC(pthread_sigmask(SIG_BLOCK, &usr1_mask, NULL));
+	TEST_SUCC(syscall(SYS_tgkill, getpid(), gettid(), SIGUSR1));
+	TEST_RES(sigpending(&pending), sigismember(&pending, SIGUSR1));
+	TEST_RES(atomic_load(&handler_tid), _ret == 0);
+	TEST_SUCC(pthread_sigmask(SIG_UNBLOCK, &usr1_mask, NULL));
+	TEST_RES(wait_handler(), _ret == gettid());
+}
+END_TEST()
+
+FN_TEST(thread_directed_invalid)
+{
+	TEST_ERRNO(syscall(SYS_tgkill, getpid() + 1, thread_tid, 0), ESRCH);
+	TEST_ERRNO(syscall(SYS_tgkill, 0, thread_tid, 0), EINVAL);
+	TEST_ERRNO(syscall(SYS_tgkill, getpid(), -1, 0), EINVAL);
+	TEST_ERRNO(syscall(SYS_tkill, 0, 0), EINVAL);
+	TEST_SUCC(syscall(SYS_tkill, thread_tid, 0));
+}
+END_TEST()
+
+static int fork_and_check_alt_stack(void)
+{
+	stack_t stack;
+	int status;
+	pid_t pid;
+
+	pid = fork();
+	if (pid < 0)
+		return -1;
+
+	if (pid == 0) {
+		// The alternate signal stack is inherited by the forked child.
+		if (sigaltstack(NULL, &stack) < 0 || (stack.ss_flags & SS_DISABLE))
+			_exit(1);
+		_exit(0);
+	}
+
+	if (waitpid(pid, &status, 0) < 0)
+		return -1;
+	return WIFEXITED(status) && WEXITSTATUS(status) == 0;
+}
+
+FN_TEST(alt_stack)
+{
+	TEST_RES(fork_and_check_alt_stack(), _ret == 1);
+}
+END_TEST()
+
+static int fault_with(int how)
+{
+	sigset_t mask;
+	int status;
+	pid_t pid;
+
+	pid = fork();
+	if (pid < 0)
+		return -1;
+
+	if (pid == 0) {
+		if (how == 0) {
+			sigemptyset(&mask);
+			sigaddset(&mask, SIGSEGV);
+			sigprocmask(SIG_BLOCK, &mask, NULL);
+		} else {
+			signal(SIGSEGV, SIG_IGN);
+		}
+		*(volatile int *)0 = 0;
+		_exit(0);
+	}
+
+	if (waitpid(pid, &status, 0) < 0)
+		return -1;
+	return WIFSIGNALED(status) && WTERMSIG(status) == SIGSEGV;
+}
+
+FN_TEST(fault_cannot_be_blocked)
+{
+	TEST_RES(fault_with(0), _ret == 1);
+	TEST_RES(fault_with(1), _ret == 1);
+}
+END_TEST()
+
+FN_SETUP(join_thread)
+{
+	void *ret;
+
+	atomic_store(&thread_exit, 1);
+	CHECK_WITH(pthread_join(thread, &ret), _ret == 0 && ret == NULL);
+}
+END_SETUP()
@@ -0,0 +1,201 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+#include <fcntl.h>
+#include <limits.h>
+#include <sound/asound.h>
+#include <sys/ioctl.h>
+#include <unistd.h>
+
+#define PCM_PATH "/dev/snd/pcmC0D0p"
+
+#define CHANNELS 2
+#define RATE 48000
+#define PERIOD_FRAMES 1024
+#define PERIODS 4
+#define FRAME_BYTES (CHANNELS * 2)
+
+static int pcm_fd;
+static short frames[PERIOD_FRAMES * PERIODS * 2][CHANNELS];
+
+static struct snd_mask *hw_mask(struct snd_pcm_hw_params *params, int param)
+{
+	return &params->masks[param - SNDRV_PCM_HW_PARAM_FIRST_MASK];
+}
+
+static struct snd_interval *hw_interval(struct snd_pcm_hw_params *params,
+					int param)
+{
+	return &params->intervals[param - SNDRV_PCM_HW_PARAM_FIRST_INTERVAL];
+}
+
+static void init_hw_params(struct snd_pcm_hw_params *params)
+{
+	int i;
+
+	memset(params, 0, sizeof(*params));
+	for (i = 0; i <= SNDRV_PCM_HW_PARAM_LAST_MASK; i++)
+		memset(hw_mask(params, i), 0xff, sizeof(struct snd_mask));
+	for (i = SNDRV_PCM_HW_PARAM_FIRST_INTERVAL;
+	     i <= SNDRV_PCM_HW_PARAM_LAST_INTERVAL; i++)
+		hw_interval(params, i)->max = UINT_MAX;
+	params->rmask = ~0U;
+}
+
+static void set_mask(struct snd_pcm_hw_params *params, int param,
+		     unsigned int value)
+{
+	struct snd_mask *mask = hw_mask(params, param);
+
+	memset(mask, 0, sizeof(*mask));
+	mask->bits[value / 32] = 1U << (value % 32);
+}
+
+static void set_interval(struct snd_pcm_hw_params *params, int param,
+			 unsigned int value)
+{
+	struct snd_interval *interval = hw_interval(params, param);
+
+	interval->min = value;
+	interval->max = value;
+	interval->integer = 1;
+}
+
+static void init_playback_params(struct snd_pcm_hw_params *params)
+{
+	init_hw_params(params);
+	set_mask(params, SNDRV_PCM_HW_PARAM_ACCESS,
+		 SNDRV_PCM_ACCESS_RW_INTERLEAVED);
+	set_mask(params, SNDRV_PCM_HW_PARAM_FORMAT, SNDRV_PCM_FORMAT_S16_LE);
+	set_interval(params, SNDRV_PCM_HW_PARAM_CHANNELS, CHANNELS);
+	set_interval(params, SNDRV_PCM_HW_PARAM_RATE, RATE);
+	set_interval(params, SNDRV_PCM_HW_PARAM_PERIOD_SIZE, PERIOD_FRAMES);
+	set_interval(params, SNDRV_PCM_HW_PARAM_PERIODS, PERIODS);
+}
+
+FN_SETUP(open)
+{
+	unsigned int i;
+
+	pcm_fd = CHECK(open(PCM_PATH, O_WRONLY));
+
+	// A square wave, which is easy to recognize if the host plays it.
+	for (i = 0; i < sizeof(frames) / sizeof(frames[0]); i++) {
+		frames[i][0] = (i / 64) % 2 ? 0x2000 : -0x2000;
+		frames[i][1] = frames[i][0];
+	}
+}
+END_SETUP()
+
+FN_TEST(version_and_info)
+{
+	int version;
+	struct snd_pcm_info info;
+
+	TEST_RES(ioctl(pcm_fd, SNDRV_PCM_IOCTL_PVERSION, &version),
+		 SNDRV_PROTOCOL_MAJOR(version) == 2);
+	TEST_RES(ioctl(pcm_fd, SNDRV_PCM_IOCTL_INFO, &info),
+		 info.device == 0 && info.subdevice == 0 &&
+			 info.stream == SNDRV_PCM_STREAM_PLAYBACK &&
+			 info.subdevices_count == 1);
+}
+END_TEST()
+
+FN_TEST(open_twice)
+{
+	TEST_ERRNO(open(PCM_PATH, O_WRONLY), EBUSY);
+}
+END_TEST()
+
+FN_TEST(not_set_up)
+{
+	TEST_ERRNO(ioctl(pcm_fd, SNDRV_PCM_IOCTL_PREPARE), EBADFD);
+	TEST_ERRNO(ioctl(pcm_fd, SNDRV_PCM_IOCTL_DROP), EBADFD);
+	TEST_ERRNO(write(pcm_fd, frames, sizeof(frames)), EBADFD);
+	TEST_ERRNO(read(pcm_fd, frames, sizeof(frames)), EINVAL);
+}
+END_TEST()
+
+FN_TEST(invalid_hw_params)
+{
+	struct snd_pcm_hw_params params;
+
+	init_playback_params(&params);
+	set_mask(&params, SNDRV_PCM_HW_PARAM_ACCESS,
+		 SNDRV_PCM_ACCESS_MMAP_INTERLEAVED);
+	TEST_ERRNO(ioctl(pcm_fd, SNDRV_PCM_IOCTL_HW_PARAMS, &params), EINVAL);
+
+	init_playback_params(&params);
+	set_interval(&params, SNDRV_PCM_HW_PARAM_CHANNELS, 0);
+	TEST_ERRNO(ioctl(pcm_fd, SNDRV_PCM_IOCTL_HW_PARAMS, &params), EINVAL);
+
+	init_playback_params(&params);
+	set_interval(&params, SNDRV_PCM_HW_PARAM_RATE, 12345);
+	TEST_ERRNO(ioctl(pcm_fd, SNDRV_PCM_IOCTL_HW_PARAMS, &params), EINVAL);
+}
+END_TEST()
+
+FN_TEST(hw_params)
+{
+	struct snd_pcm_hw_params params;
+
+	init_playback_params(&params);
+	TEST_SUCC(ioctl(pcm_fd, SNDRV_PCM_IOCTL_HW_PARAMS, &params));
+
+	TEST_RES(hw_interval(&params, SNDRV_PCM_HW_PARAM_CHANNELS)->min,
+		 _ret == CHANNELS);
+	TEST_RES(hw_interval(&params, SNDRV_PCM_HW_PARAM_RATE)->min,
+		 _ret == RATE);
+	TEST_RES(hw_interval(&params, SNDRV_PCM_HW_PARAM_PERIOD_BYTES)->min,
+		 _ret == PERIOD_FRAMES * FRAME_BYTES);
+	TEST_RES(hw_interval(&params, SNDRV_PCM_HW_PARAM_BUFFER_SIZE)->min,
+		 _ret == PERIOD_FRAMES * PERIODS);
+}
+END_TEST()
+
+FN_TEST(write_and_drain)
+{
+	struct snd_xferi xferi;
+
+	TEST_SUCC(ioctl(pcm_fd, SNDRV_PCM_IOCTL_PREPARE));
+
+	// Only whole frames are written.
+	TEST_RES(write(pcm_fd, frames, FRAME_BYTES - 1), _ret == 0);
+	TEST_RES(write(pcm_fd, frames, FRAME_BYTES * 3 + 1),
+		 _ret == FRAME_BYTES * 3);
+
+	// More frames than the buffer can hold are written, so the playback
+	// starts and the write blocks until some periods are played.
+	xferi.buf = frames;
+	xferi.frames = sizeof(frames) / FRAME_BYTES;
+	TEST_RES(ioctl(pcm_fd, SNDRV_PCM_IOCTL_WRITEI_FRAMES, &xferi),
+		 xferi.result == sizeof(frames) / FRAME_BYTES);
+
+	TEST_SUCC(ioctl(pcm_fd, SNDRV_PCM_IOCTL_DRAIN));
+	TEST_ERRNO(write(pcm_fd, frames, sizeof(frames)), EBADFD);
+}
+END_TEST()
+
+FN_TEST(start_and_drop)
+{
+	TEST_SUCC(ioctl(pcm_fd, SNDRV_PCM_IOCTL_PREPARE));
+	TEST_RES(write(pcm_fd, frames, PERIOD_FRAMES * FRAME_BYTES),
+		 _ret == PERIOD_FRAMES * FRAME_BYTES);
+	TEST_SUCC(ioctl(pcm_fd, SNDRV_PCM_IOCTL_START));
+	TEST_ERRNO(ioctl(pcm_fd, SNDRV_PCM_IOCTL_START), EBADFD);
+
+	TEST_SUCC(ioctl(pcm_fd, SNDRV_PCM_IOCTL_DROP));
+	TEST_ERRNO(ioctl(pcm_fd, SNDRV_PCM_IOCTL_START), EBADFD);
+
+	TEST_SUCC(ioctl(pcm_fd, SNDRV_PCM_IOCTL_HW_FREE));
+	TEST_ERRNO(ioctl(pcm_fd, SNDRV_PCM_IOCTL_PREPARE), EBADFD);
+}
+END_TEST()
+
+FN_SETUP(close)
+{
+	CHECK(close(pcm_fd));
+}
+END_SETUP()
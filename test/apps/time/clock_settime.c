@@ -0,0 +1,114 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <sys/timerfd.h>
+#include <sys/timex.h>
+#include <time.h>
+#include <unistd.h>
+
+static struct timespec realtime, monotonic;
+
+static long long diff_secs(const struct timespec *now,
+			   const struct timespec *before)
+{
+	return now->tv_sec - before->tv_sec;
+}
+
+static int set_realtime_after(long long secs)
+{
+	struct timespec now;
+
+	CHECK(clock_gettime(CLOCK_REALTIME, &now));
+	now.tv_sec += secs;
+	return clock_settime(CLOCK_REALTIME, &now);
+}
+
+FN_SETUP(read_clocks)
+{
+	CHECK(clock_gettime(CLOCK_REALTIME, &realtime));
+	CHECK(clock_gettime(CLOCK_MONOTONIC, &monotonic));
+}
+END_SETUP()
+
+FN_TEST(invalid_settime)
+{
+	struct timespec ts = { .tv_sec = realtime.tv_sec, .tv_nsec = -1 };
+
+	TEST_ERRNO(clock_settime(CLOCK_MONOTONIC, &monotonic), EINVAL);
+	TEST_ERRNO(clock_settime(CLOCK_REALTIME, &ts), EINVAL);
+
+	// The real time cannot be set before the monotonic time.
+	ts.tv_sec = 0;
+	ts.tv_nsec = 0;
+	TEST_ERRNO(clock_settime(CLOCK_REALTIME, &ts), EINVAL);
+}
+END_TEST()
+
+FN_TEST(settime)
+{
+	struct timespec now;
+
+	TEST_SUCC(set_realtime_after(1000));
+	TEST_RES(clock_gettime(CLOCK_REALTIME, &now),
+		 diff_secs(&now, &realtime) >= 1000);
+	TEST_RES(clock_gettime(CLOCK_MONOTONIC, &now),
+		 diff_secs(&now, &monotonic) < 1000);
+
+	TEST_SUCC(set_realtime_after(-1000));
+	TEST_RES(clock_gettime(CLOCK_REALTIME, &now),
+		 diff_secs(&now, &realtime) < 1000);
+}
+END_TEST()
+
+FN_TEST(adjtimex)
+{
+	struct timex tx = { .modes = 0 };
+	struct timespec now;
+
+	TEST_RES(adjtimex(&tx), _ret >= 0 && tx.time.tv_sec >= realtime.tv_sec);
+
+	tx.modes = ADJ_SETOFFSET;
+	tx.time.tv_sec = 1000;
+	tx.time.tv_usec = 0;
+	TEST_RES(adjtimex(&tx), _ret >= 0);
+	TEST_RES(clock_gettime(CLOCK_REALTIME, &now),
+		 diff_secs(&now, &realtime) >= 1000);
+
+	tx.modes = ADJ_SETOFFSET;
+	tx.time.tv_sec = -1000;
+	tx.time.tv_usec = 0;
+	TEST_RES(adjtimex(&tx), _ret >= 0);
+	TEST_RES(clock_gettime(CLOCK_REALTIME, &now),
+		 diff_secs(&now, &realtime) < 1000);
+
+	tx.modes = ADJ_SETOFFSET;
+	tx.time.tv_sec = 0;
+	tx.time.tv_usec = 1000000;
+	TEST_ERRNO(adjtimex(&tx), EINVAL);
+}
+END_TEST()
+
+FN_TEST(timerfd_cancel_on_set)
+{
+	struct itimerspec its = { .it_value = realtime };
+	unsigned long long ticks;
+	int fd;
+
+	fd = TEST_SUCC(timerfd_create(CLOCK_REALTIME, TFD_NONBLOCK));
+	TEST_ERRNO(timerfd_settime(fd, 4, &its, NULL), EINVAL);
+
+	its.it_value.tv_sec += 3600;
+	TEST_SUCC(timerfd_settime(
+		fd, TFD_TIMER_ABSTIME | TFD_TIMER_CANCEL_ON_SET, &its, NULL));
+	TEST_ERRNO(read(fd, &ticks, sizeof(ticks)), EAGAIN);
+
+	TEST_SUCC(set_realtime_after(0));
+	TEST_ERRNO(read(fd, &ticks, sizeof(ticks)), ECANCELED);
+	TEST_ERRNO(read(fd, &ticks, sizeof(ticks)), EAGAIN);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
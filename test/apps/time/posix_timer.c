@@ -0,0 +1,179 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <signal.h>
+#include <sys/syscall.h>
+#include <time.h>
+#include <unistd.h>
+
+#define NSEC_PER_SEC 1000000000L
+
+// See `MAKE_PROCESS_CPUCLOCK` and `MAKE_THREAD_CPUCLOCK` in Linux.
+#define CPUCLOCK_SCHED 2
+#define CPUCLOCK_PERTHREAD_MASK 4
+#define MAKE_PROCESS_CPUCLOCK(pid, clock) \
+	((int)(~(unsigned int)(pid) << 3) | (clock))
+#define MAKE_THREAD_CPUCLOCK(tid, clock) \
+	MAKE_PROCESS_CPUCLOCK(tid, (clock) | CPUCLOCK_PERTHREAD_MASK)
+
+static volatile int nr_signals;
+
+static void handle_signal(int sig)
+{
+	++nr_signals;
+}
+
+static long timespec_to_ns(const struct timespec *ts)
+{
+	return ts->tv_sec * NSEC_PER_SEC + ts->tv_nsec;
+}
+
+static int clock_is_monotonic(clockid_t clockid)
+{
+	struct timespec before, after;
+
+	if (clock_gettime(clockid, &before) < 0)
+		return 0;
+	if (clock_gettime(clockid, &after) < 0)
+		return 0;
+
+	return timespec_to_ns(&after) >= timespec_to_ns(&before);
+}
+
+static long clock_res_ns(clockid_t clockid)
+{
+	struct timespec res;
+
+	if (clock_getres(clockid, &res) < 0)
+		return -1;
+
+	return timespec_to_ns(&res);
+}
+
+static void spin_for_ms(long ms)
+{
+	struct timespec begin, now;
+
+	CHECK(clock_gettime(CLOCK_MONOTONIC, &begin));
+	do {
+		CHECK(clock_gettime(CLOCK_MONOTONIC, &now));
+	} while (timespec_to_ns(&now) - timespec_to_ns(&begin) <
+		 ms * 1000000L);
+}
+
+FN_SETUP(signal)
+{
+	CHECK(signal(SIGUSR1, handle_signal) == SIG_ERR ? -1 : 0);
+}
+END_SETUP()
+
+FN_TEST(clock_gettime)
+{
+	TEST_RES(clock_is_monotonic(CLOCK_MONOTONIC_RAW), _ret);
+	TEST_RES(clock_is_monotonic(CLOCK_BOOTTIME), _ret);
+	TEST_RES(clock_is_monotonic(CLOCK_PROCESS_CPUTIME_ID), _ret);
+	TEST_RES(clock_is_monotonic(CLOCK_THREAD_CPUTIME_ID), _ret);
+	TEST_RES(clock_is_monotonic(MAKE_PROCESS_CPUCLOCK(getpid(),
+							    CPUCLOCK_SCHED)),
+		 _ret);
+	TEST_RES(clock_is_monotonic(MAKE_THREAD_CPUCLOCK(syscall(SYS_gettid),
+							   CPUCLOCK_SCHED)),
+		 _ret);
+}
+END_TEST()
+
+FN_TEST(clock_getres)
+{
+	TEST_RES(clock_res_ns(CLOCK_REALTIME), _ret > 0 && _ret <= 1000000);
+	TEST_RES(clock_res_ns(CLOCK_MONOTONIC), _ret > 0 && _ret <= 1000000);
+	TEST_RES(clock_res_ns(CLOCK_MONOTONIC_RAW),
+		 _ret > 0 && _ret <= 1000000);
+	TEST_RES(clock_res_ns(CLOCK_BOOTTIME), _ret > 0 && _ret <= 1000000);
+	TEST_RES(clock_res_ns(CLOCK_MONOTONIC_COARSE), _ret > 0);
+	TEST_RES(clock_res_ns(CLOCK_PROCESS_CPUTIME_ID), _ret > 0);
+	TEST_SUCC(clock_getres(CLOCK_MONOTONIC, NULL));
+
+	TEST_ERRNO(clock_getres(-1, NULL), EINVAL);
+	TEST_ERRNO(clock_getres(100, NULL), EINVAL);
+}
+END_TEST()
+
+FN_TEST(timer_signal)
+{
+	struct sigevent sev = {
+		.sigev_notify = SIGEV_SIGNAL,
+		.sigev_signo = SIGUSR1,
+	};
+	struct itimerspec its = {
+		.it_value = { .tv_nsec = 10 * 1000000 },
+		.it_interval = { .tv_nsec = 10 * 1000000 },
+	};
+	struct itimerspec old;
+	timer_t timerid;
+
+	TEST_SUCC(timer_create(CLOCK_MONOTONIC, &sev, &timerid));
+
+	nr_signals = 0;
+	TEST_SUCC(timer_settime(timerid, 0, &its, NULL));
+	while (nr_signals < 3)
+		pause();
+
+	// Disarms the timer.
+	its.it_value.tv_nsec = 0;
+	TEST_RES(timer_settime(timerid, 0, &its, &old),
+		 old.it_interval.tv_sec == 0 &&
+			 old.it_interval.tv_nsec == 10 * 1000000);
+	TEST_RES(timer_gettime(timerid, &old),
+		 old.it_value.tv_sec == 0 && old.it_value.tv_nsec == 0);
+
+	TEST_SUCC(timer_delete(timerid));
+}
+END_TEST()
+
+FN_TEST(timer_cputime)
+{
+	struct sigevent sev = {
+		.sigev_notify = SIGEV_SIGNAL,
+		.sigev_signo = SIGUSR1,
+	};
+	struct itimerspec its = {
+		.it_value = { .tv_nsec = 20 * 1000000 },
+	};
+	timer_t timerid;
+
+	TEST_SUCC(timer_create(CLOCK_PROCESS_CPUTIME_ID, &sev, &timerid));
+
+	// The timer only fires if the process consumes the CPU time.
+	nr_signals = 0;
+	TEST_SUCC(timer_settime(timerid, 0, &its, NULL));
+	while (nr_signals == 0)
+		spin_for_ms(10);
+
+	TEST_SUCC(timer_delete(timerid));
+}
+END_TEST()
+
+FN_TEST(timer_none)
+{
+	struct sigevent sev = {
+		.sigev_notify = SIGEV_NONE,
+	};
+	struct itimerspec its = {
+		.it_value = { .tv_sec = 100 },
+	};
+	struct itimerspec cur;
+	timer_t timerid;
+
+	TEST_SUCC(timer_create(CLOCK_BOOTTIME, &sev, &timerid));
+	TEST_SUCC(timer_settime(timerid, 0, &its, NULL));
+	TEST_RES(timer_gettime(timerid, &cur),
+		 cur.it_value.tv_sec > 0 && cur.it_value.tv_sec <= 100);
+
+	TEST_SUCC(timer_delete(timerid));
+	TEST_ERRNO(timer_delete(timerid), EINVAL);
+	TEST_ERRNO(timer_gettime(timerid, &cur), EINVAL);
+}
+END_TEST()
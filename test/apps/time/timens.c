@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <sched.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#ifndef CLONE_NEWTIME
+#define CLONE_NEWTIME 0x00000080
+#endif
+
+#define OFFSET_SECS 100000
+
+static int write_offsets(const char *offsets)
+{
+	int fd, ret, err;
+
+	fd = CHECK(open("/proc/self/timens_offsets", O_WRONLY));
+	ret = write(fd, offsets, strlen(offsets));
+	err = errno;
+	CHECK(close(fd));
+
+	errno = err;
+	return ret < 0 ? -1 : 0;
+}
+
+static int read_offsets(char *buf, size_t len)
+{
+	int fd, ret;
+
+	fd = CHECK(open("/proc/self/timens_offsets", O_RDONLY));
+	ret = read(fd, buf, len - 1);
+	CHECK(close(fd));
+
+	if (ret < 0)
+		return -1;
+	buf[ret] = '\0';
+	return ret;
+}
+
+// Returns the difference of `clockid` between a child process and the current process.
+static int child_clock_diff(clockid_t clockid)
+{
+	struct timespec parent_time, child_time;
+	int pipefd[2];
+	pid_t pid;
+	int status;
+
+	CHECK(pipe(pipefd));
+	CHECK(clock_gettime(clockid, &parent_time));
+
+	pid = CHECK(fork());
+	if (pid == 0) {
+		CHECK(clock_gettime(clockid, &child_time));
+		CHECK(write(pipefd[1], &child_time, sizeof(child_time)));
+		_exit(0);
+	}
+
+	CHECK(read(pipefd[0], &child_time, sizeof(child_time)));
+	CHECK(waitpid(pid, &status, 0));
+	CHECK(close(pipefd[0]));
+	CHECK(close(pipefd[1]));
+
+	return child_time.tv_sec - parent_time.tv_sec;
+}
+
+FN_TEST(initial_offsets)
+{
+	char buf[128];
+
+	TEST_RES(read_offsets(buf, sizeof(buf)),
+		 strcmp(buf, "monotonic           0         0\n"
+			     "boottime            0         0\n") == 0);
+
+	// The initial time namespace has already been entered.
+	TEST_ERRNO(write_offsets("monotonic 1 0\n"), EACCES);
+}
+END_TEST()
+
+FN_TEST(unshare_newtime)
+{
+	char buf[128];
+
+	TEST_SUCC(unshare(CLONE_NEWTIME));
+
+	// The offsets are validated.
+	TEST_ERRNO(write_offsets("monotonic 1 1000000000\n"), EINVAL);
+	TEST_ERRNO(write_offsets("monotonic 1 -1\n"), EINVAL);
+	TEST_ERRNO(write_offsets("realtime 1 0\n"), EINVAL);
+	TEST_ERRNO(write_offsets("0 1 0\n"), EINVAL);
+	TEST_ERRNO(write_offsets("monotonic -100000000000 0\n"), ERANGE);
+
+	TEST_SUCC(write_offsets("monotonic 100000 0\n"
+				"7 200000 500\n"));
+	TEST_RES(read_offsets(buf, sizeof(buf)),
+		 strcmp(buf, "monotonic      100000         0\n"
+			     "boottime       200000       500\n") == 0);
+
+	// The current process is not in the new time namespace.
+	TEST_RES(child_clock_diff(CLOCK_MONOTONIC),
+		 _ret >= OFFSET_SECS && _ret <= OFFSET_SECS + 1);
+	TEST_RES(child_clock_diff(CLOCK_BOOTTIME),
+		 _ret >= 2 * OFFSET_SECS && _ret <= 2 * OFFSET_SECS + 1);
+	TEST_RES(child_clock_diff(CLOCK_REALTIME), _ret <= 1);
+
+	// The offsets are frozen after a process enters the time namespace.
+	TEST_ERRNO(write_offsets("monotonic 0 0\n"), EACCES);
+}
+END_TEST()
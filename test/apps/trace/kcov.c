@@ -0,0 +1,114 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <stdint.h>
+#include <sys/ioctl.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+#define KCOV_INIT_TRACE _IOR('c', 1, unsigned long)
+#define KCOV_ENABLE _IO('c', 100)
+#define KCOV_DISABLE _IO('c', 101)
+
+#define KCOV_TRACE_PC 0
+#define KCOV_TRACE_CMP 1
+
+#define COVER_SIZE (64 * 1024)
+
+static int fd, other_fd;
+static uint64_t *cover;
+
+FN_SETUP(open)
+{
+	fd = CHECK(open("/sys/kernel/debug/kcov", O_RDWR));
+	other_fd = CHECK(open("/sys/kernel/debug/kcov", O_RDWR));
+}
+END_SETUP()
+
+FN_TEST(uninitialized)
+{
+	TEST_ERRNO(ioctl(fd, KCOV_ENABLE, KCOV_TRACE_PC), EINVAL);
+	TEST_ERRNO((long)mmap(NULL, COVER_SIZE * sizeof(uint64_t),
+			      PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0),
+		   EINVAL);
+}
+END_TEST()
+
+FN_TEST(init_trace)
+{
+	TEST_ERRNO(ioctl(fd, KCOV_INIT_TRACE, 1), EINVAL);
+	TEST_SUCC(ioctl(fd, KCOV_INIT_TRACE, COVER_SIZE));
+	TEST_ERRNO(ioctl(fd, KCOV_INIT_TRACE, COVER_SIZE), EBUSY);
+	TEST_SUCC(ioctl(other_fd, KCOV_INIT_TRACE, COVER_SIZE));
+
+	cover = (uint64_t *)CHECK_WITH(
+		(long)mmap(NULL, COVER_SIZE * sizeof(uint64_t),
+			   PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0),
+		_ret != (long)MAP_FAILED);
+}
+END_TEST()
+
+FN_TEST(enable_and_disable)
+{
+	TEST_ERRNO(ioctl(fd, KCOV_ENABLE, KCOV_TRACE_CMP), EOPNOTSUPP);
+	TEST_ERRNO(ioctl(fd, KCOV_DISABLE, 0), EINVAL);
+
+	TEST_SUCC(ioctl(fd, KCOV_ENABLE, KCOV_TRACE_PC));
+	TEST_ERRNO(ioctl(fd, KCOV_ENABLE, KCOV_TRACE_PC), EBUSY);
+	TEST_ERRNO(ioctl(other_fd, KCOV_ENABLE, KCOV_TRACE_PC), EBUSY);
+	TEST_ERRNO(ioctl(other_fd, KCOV_DISABLE, 0), EINVAL);
+	TEST_ERRNO(ioctl(fd, KCOV_DISABLE, 1), EINVAL);
+
+	TEST_SUCC(ioctl(fd, KCOV_DISABLE, 0));
+	TEST_ERRNO(ioctl(fd, KCOV_DISABLE, 0), EINVAL);
+
+	// The trace can be enabled again, by the same file or another one.
+	TEST_SUCC(ioctl(other_fd, KCOV_ENABLE, KCOV_TRACE_PC));
+	TEST_SUCC(ioctl(other_fd, KCOV_DISABLE, 0));
+}
+END_TEST()
+
+FN_TEST(collect)
+{
+	uint64_t nr_pcs;
+
+	// The PCs are recorded only if the kernel is instrumented, so the
+	// number of the PCs may be zero.
+	TEST_SUCC(ioctl(fd, KCOV_ENABLE, KCOV_TRACE_PC));
+	__atomic_store_n(&cover[0], 0, __ATOMIC_RELAXED);
+	TEST_SUCC(getppid());
+	nr_pcs = __atomic_load_n(&cover[0], __ATOMIC_RELAXED);
+	TEST_SUCC(ioctl(fd, KCOV_DISABLE, 0));
+
+	TEST_RES(nr_pcs, _ret < COVER_SIZE);
+	if (nr_pcs > 0)
+		// The PCs are in the higher half of the address space.
+		TEST_RES(cover[1] >> 63, _ret == 1);
+
+	// No PCs are recorded after the trace is disabled.
+	__atomic_store_n(&cover[0], 0, __ATOMIC_RELAXED);
+	TEST_SUCC(getppid());
+	TEST_RES(__atomic_load_n(&cover[0], __ATOMIC_RELAXED), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(read_write)
+{
+	char buf[8];
+
+	TEST_ERRNO(read(fd, buf, sizeof(buf)), EINVAL);
+	TEST_ERRNO(write(fd, buf, sizeof(buf)), EINVAL);
+}
+END_TEST()
+
+FN_SETUP(close)
+{
+	CHECK(munmap(cover, COVER_SIZE * sizeof(uint64_t)));
+	CHECK(close(other_fd));
+	CHECK(close(fd));
+}
+END_SETUP()
@@ -0,0 +1,146 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define ROOT "/sys/kernel/tracing"
+#define SYS_ENTER "raw_syscalls:sys_enter"
+
+static char buf[1 << 20];
+
+static int write_file(const char *path, int flags, const char *contents)
+{
+	ssize_t len;
+	int fd;
+
+	fd = open(path, O_WRONLY | flags);
+	if (fd < 0)
+		return -1;
+	len = write(fd, contents, strlen(contents));
+	close(fd);
+
+	return len < 0 ? -1 : 0;
+}
+
+static ssize_t read_file(const char *path)
+{
+	ssize_t len, total = 0;
+	int fd;
+
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+	while ((len = read(fd, buf + total, sizeof(buf) - 1 - total)) > 0)
+		total += len;
+	close(fd);
+	if (len < 0)
+		return -1;
+
+	buf[total] = '\0';
+	return total;
+}
+
+static int read_file_and_check(const char *path, const char *expected)
+{
+	if (read_file(path) < 0)
+		return -1;
+	return strcmp(buf, expected) == 0;
+}
+
+static int read_file_and_find(const char *path, const char *needle)
+{
+	if (read_file(path) < 0)
+		return -1;
+	return strstr(buf, needle) != NULL;
+}
+
+FN_SETUP(disable_events)
+{
+	CHECK(write_file(ROOT "/set_event", O_TRUNC, ""));
+	CHECK(write_file(ROOT "/trace", O_TRUNC, ""));
+}
+END_SETUP()
+
+FN_TEST(available_events)
+{
+	TEST_RES(read_file_and_find(ROOT "/available_events",
+				    "sched:sched_switch\n"),
+		 _ret == 1);
+	TEST_RES(read_file_and_find(ROOT "/available_events", SYS_ENTER "\n"),
+		 _ret == 1);
+	TEST_RES(read_file_and_find(ROOT "/events/raw_syscalls/sys_enter/format",
+				    "\targ5: hex\n"),
+		 _ret == 1);
+	TEST_RES(read_file_and_check(ROOT "/set_event", ""), _ret == 1);
+}
+END_TEST()
+
+FN_TEST(invalid_writes)
+{
+	TEST_ERRNO(write_file(ROOT "/set_event", O_APPEND, "nonexistent:event"),
+		   EINVAL);
+	TEST_ERRNO(write_file(ROOT "/tracing_on", 0, "2"), EINVAL);
+	TEST_ERRNO(write_file(ROOT "/events/sched/sched_switch/enable", 0,
+			      "yes"),
+		   EINVAL);
+	TEST_ERRNO(write_file(ROOT "/available_events", 0, "sched"), EACCES);
+	TEST_ERRNO(unlink(ROOT "/trace"), EPERM);
+}
+END_TEST()
+
+FN_TEST(trace_syscalls)
+{
+	char needle[64];
+
+	TEST_SUCC(write_file(ROOT "/set_event", O_TRUNC, SYS_ENTER));
+	TEST_RES(read_file_and_check(ROOT "/set_event", SYS_ENTER "\n"),
+		 _ret == 1);
+	TEST_RES(read_file_and_check(ROOT "/events/raw_syscalls/sys_enter/enable",
+				     "1\n"),
+		 _ret == 1);
+
+	TEST_SUCC(syscall(SYS_getpid));
+	snprintf(needle, sizeof(needle), "sys_enter: tid=%d nr=%d ", gettid(),
+		 SYS_getpid);
+	TEST_RES(read_file_and_find(ROOT "/trace", needle), _ret == 1);
+	TEST_RES(read_file_and_find(ROOT "/trace", "# tracer: nop\n"),
+		 _ret == 1);
+}
+END_TEST()
+
+FN_TEST(tracing_on)
+{
+	TEST_SUCC(write_file(ROOT "/tracing_on", 0, "0"));
+	TEST_RES(read_file_and_check(ROOT "/tracing_on", "0\n"), _ret == 1);
+
+	// No events are recorded after the events are cleared.
+	TEST_SUCC(write_file(ROOT "/trace", O_TRUNC, ""));
+	TEST_SUCC(syscall(SYS_getpid));
+	TEST_RES(read_file_and_find(ROOT "/trace", "sys_enter:"), _ret == 0);
+
+	TEST_SUCC(write_file(ROOT "/tracing_on", 0, "1"));
+	TEST_SUCC(syscall(SYS_getpid));
+	TEST_RES(read_file_and_find(ROOT "/trace", "sys_enter:"), _ret == 1);
+}
+END_TEST()
+
+FN_TEST(disable_events)
+{
+	TEST_SUCC(write_file(ROOT "/set_event", O_APPEND, "!" SYS_ENTER));
+	TEST_RES(read_file_and_check(ROOT "/set_event", ""), _ret == 1);
+	TEST_RES(read_file_and_check(ROOT "/events/raw_syscalls/sys_enter/enable",
+				     "0\n"),
+		 _ret == 1);
+
+	TEST_SUCC(write_file(ROOT "/trace", O_TRUNC, ""));
+	TEST_SUCC(syscall(SYS_getpid));
+	TEST_RES(read_file_and_find(ROOT "/trace", "sys_enter:"), _ret == 0);
+}
+END_TEST()
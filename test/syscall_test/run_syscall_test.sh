@@ -2,11 +2,34 @@
 
 # SPDX-License-Identifier: MPL-2.0
 
+# Usage: run_syscall_test.sh [SUITE]...
+#
+# The test suites to run are given by the arguments, or by the comma-separated
+# `SYSCALL_TEST_SUITES` environment variable. All the installed test suites are
+# run if neither is given.
+#
+# Besides the human-readable output, the result of each test suite is reported
+# with a machine-readable line like the following, so that the results can be
+# collected from the console and tracked suite by suite:
+#
+#   [syscall-test] suite=open_test result=failed cases=42 failed=2 blocked=3
+#
+# The possible results are `passed`, `failed`, `crashed` (the test binary died
+# before reporting its results) and `missing` (the test binary is not
+# installed). Each failed test case is also reported with a line like:
+#
+#   [syscall-test-case] suite=open_test case=OpenTest.ReadOnly result=failed
+#
+# The reports are written to `/dev/kmsg` if it is available, so that they are
+# printed on the kernel console without being interleaved with the outputs of
+# the tests.
+
 SCRIPT_DIR=$(dirname "$0")
 TEST_TMP_DIR=${SYSCALL_TEST_DIR:-/tmp}
 TEST_BIN_DIR=$SCRIPT_DIR/tests
 BLOCKLIST_DIR=$SCRIPT_DIR/blocklists
 FAIL_CASES=$SCRIPT_DIR/fail_cases
+LOG_DIR=$SCRIPT_DIR/logs
 BLOCK=""
 TESTS=0
 PASSED_TESTS=0
@@ -31,30 +54,75 @@ get_blocklist_subtests(){
     return 0
 }
 
+report(){
+    if [ -w /dev/kmsg ]; then
+        echo "$1" > /dev/kmsg
+    else
+        echo "$1"
+    fi
+}
+
+# Reports the result of the test suite from the output of the test binary.
+report_one_test(){
+    log=$LOG_DIR/$1.log
+    blocked=$(echo "$BLOCK" | tr ':' '\n' | grep -c .)
+
+    if [ ! -f $log ]; then
+        report "[syscall-test] suite=$1 result=missing cases=0 failed=0 blocked=$blocked"
+        return
+    fi
+
+    cases=$(sed -n 's/^\[==========\] \([0-9]*\) tests* from .* ran\..*/\1/p' $log)
+    failed=$(sed -n 's/^\[  FAILED  \] \([0-9]*\) tests*, listed below:.*/\1/p' $log)
+    if [ -z "$cases" ]; then
+        result=crashed
+    elif [ $2 -eq 0 ]; then
+        result=passed
+    else
+        result=failed
+    fi
+    report "[syscall-test] suite=$1 result=$result cases=${cases:-0} failed=${failed:-0} blocked=$blocked"
+
+    for case in $(sed -n 's/^\[  FAILED  \] \([^ ,]*\).*/\1/p' $log | grep -v '^[0-9]' | sort -u); do
+        report "[syscall-test-case] suite=$1 case=$case result=failed"
+    done
+}
+
 run_one_test(){
     echo -e "Run Test Case: $1"
     # The gvisor test framework utilizes the "TEST_TMPDIR" environment variable to dictate the directory's location.
     export TEST_TMPDIR=$TEST_TMP_DIR
     ret=0
+    get_blocklist_subtests $1
     if [ -f $TEST_BIN_DIR/$1 ]; then
-        get_blocklist_subtests $1
-        cd $TEST_BIN_DIR && ./$1 --gtest_filter=-$BLOCK
-        ret=$?
+        # Keep the output for the report while showing it on the console.
+        { (cd $TEST_BIN_DIR && ./$1 --gtest_filter=-$BLOCK); echo $? > $LOG_DIR/$1.ret; } 2>&1 \
+            | tee $LOG_DIR/$1.log
+        ret=$(cat $LOG_DIR/$1.ret)
         #After executing the test, it is necessary to clean the directory to ensure no residual data remains
         rm -rf $TEST_TMP_DIR/*
     else
         echo -e "Warning: $1 test does not exit"
         ret=1
     fi
+    report_one_test $1 $ret
     echo ""
     return $ret
 }
 
 rm -f $FAIL_CASES && touch $FAIL_CASES
+rm -rf $LOG_DIR && mkdir -p $LOG_DIR
 rm -rf $TEST_TMP_DIR/*
 
-for syscall_test in $(find $TEST_BIN_DIR/. -name \*_test) ; do
-    test_name=$(basename "$syscall_test")
+if [ $# -gt 0 ]; then
+    SUITES="$*"
+elif [ -n "$SYSCALL_TEST_SUITES" ]; then
+    SUITES=$(echo "$SYSCALL_TEST_SUITES" | tr ',' ' ')
+else
+    SUITES=$(find $TEST_BIN_DIR/. -name \*_test -exec basename {} \;)
+fi
+
+for test_name in $SUITES ; do
     run_one_test $test_name
     if [ $? -eq 0 ] && PASSED_TESTS=$((PASSED_TESTS+1));then
         TESTS=$((TESTS+1))
@@ -64,6 +132,7 @@ for syscall_test in $(find $TEST_BIN_DIR/. -name \*_test) ; do
     fi
 done
 
+report "[syscall-test-summary] suites=$TESTS passed=$PASSED_TESTS failed=$(($TESTS-$PASSED_TESTS))"
 echo -e "$GREEN$PASSED_TESTS$NC of $GREEN$TESTS$NC test cases passed."
 [ $PASSED_TESTS -ne $TESTS ] && RESULT=1
 if [ $TESTS != $PASSED_TESTS ]; then
@@ -0,0 +1,35 @@
+#!/bin/bash
+
+# SPDX-License-Identifier: MPL-2.0
+
+# This script checks the consistency of an Ext2 disk image with a journal
+# after the kernel recovers it.
+# Usage: `ext2_check.sh <image>`
+#
+# It's used as the checker of `cargo osdk crash-test` by `make ext2_crash_test`.
+
+set -e
+
+IMAGE_PATH="$1"
+
+if [ -z "${IMAGE_PATH}" ]; then
+    echo "Error: no disk image specified"
+    exit 1
+fi
+
+# The journal must have been replayed by the kernel.
+if ! dumpe2fs -h ${IMAGE_PATH} 2>/dev/null | grep -q "^Journal start: *0$"; then
+    echo "Error: the journal is not replayed"
+    exit 1
+fi
+
+# The kernel never unmounts the filesystem cleanly, so the flag that marks the
+# journal as in use is cleared by `e2fsck` before the read-only check.
+status=0
+e2fsck -E journal_only -y ${IMAGE_PATH} > /dev/null 2>&1 || status=$?
+if [ ${status} -ge 4 ]; then
+    echo "Error: failed to clear the recovery flag"
+    exit 1
+fi
+
+e2fsck -f -n ${IMAGE_PATH}
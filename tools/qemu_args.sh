@@ -10,13 +10,20 @@
 #  - NETDEV: "user" or "tap";
 #  - VHOST: "off" or "on";
 #  - VSOCK: "off" or "on";
+#  - SOUND: "off" or "on";
+#  - SCSI: "off" or "on";
+#  - DISK: "off" or "on";
 #  - SMP: number of CPUs;
 #  - MEM: amount of memory, e.g. "8G".
-#  - VNC_PORT: VNC port, default is "42".
+#  - VNC_PORT: VNC port, default is "42";
+#  - EXT2_IMAGE: the disk image of the Ext2 filesystem, default is "./test/build/ext2.img".
 
 OVMF=${OVMF:-"on"}
 VHOST=${VHOST:-"off"}
 VSOCK=${VSOCK:-"off"}
+SOUND=${SOUND:-"off"}
+SCSI=${SCSI:-"off"}
+DISK=${DISK:-"off"}
 NETDEV=${NETDEV:-"user"}
 
 SSH_RAND_PORT=${SSH_PORT:-$(shuf -i 1024-65535 -n 1)}
@@ -86,7 +93,7 @@ COMMON_QEMU_ARGS="\
     $NETDEV_ARGS \
     $QEMU_OPT_ARG_DUMP_PACKETS \
     -device isa-debug-exit,iobase=0xf4,iosize=0x04 \
-    -drive if=none,format=raw,id=x0,file=./test/build/ext2.img \
+    -drive if=none,format=raw,id=x0,file=${EXT2_IMAGE:-./test/build/ext2.img} \
     -drive if=none,format=raw,id=x1,file=./test/build/exfat.img \
 "
 
@@ -145,6 +152,61 @@ if [ "$VSOCK" = "on" ]; then
     fi
 fi
 
+if [ "$SOUND" = "on" ]; then
+    # The frames are discarded by the host, but are still paced by QEMU as if they were played.
+    if [ "$1" = "microvm" ]; then
+        MICROVM_QEMU_ARGS="
+            $MICROVM_QEMU_ARGS \
+            -audiodev none,id=audio0 \
+            -device virtio-sound-device,audiodev=audio0 \
+        "
+    else
+        QEMU_ARGS="
+            $QEMU_ARGS \
+            -audiodev none,id=audio0 \
+            -device virtio-sound-pci,audiodev=audio0,disable-legacy=on,disable-modern=off$IOMMU_DEV_EXTRA \
+        "
+    fi
+fi
+
+if [ "$SCSI" = "on" ]; then
+    # The disk is a 64 MiB null device, whose reads return zeros and whose writes are discarded.
+    SCSI_DISK_ARGS="\
+        -blockdev driver=null-co,node-name=scsi-disk0,size=67108864,read-zeroes=on \
+        -device scsi-hd,drive=scsi-disk0,bus=scsi0.0 \
+    "
+    if [ "$1" = "microvm" ]; then
+        MICROVM_QEMU_ARGS="
+            $MICROVM_QEMU_ARGS \
+            -device virtio-scsi-device,id=scsi0 \
+            $SCSI_DISK_ARGS \
+        "
+    else
+        QEMU_ARGS="
+            $QEMU_ARGS \
+            -device virtio-scsi-pci,id=scsi0,disable-legacy=on,disable-modern=off$IOMMU_DEV_EXTRA \
+            $SCSI_DISK_ARGS \
+        "
+    fi
+fi
+
+if [ "$DISK" = "on" ]; then
+    # The disk is a blank image without a serial ID, so it is named `vda` by the kernel.
+    DISK_ARGS="-drive if=none,format=raw,id=x2,file=./test/build/disk.img"
+    if [ "$1" = "microvm" ]; then
+        MICROVM_QEMU_ARGS="
+            $MICROVM_QEMU_ARGS \
+            $DISK_ARGS \
+            -device virtio-blk-device,drive=x2 \
+        "
+    else
+        QEMU_ARGS="
+            $QEMU_ARGS \
+            $DISK_ARGS \
+            -device virtio-blk-pci,drive=x2,disable-legacy=on,disable-modern=off$IOMMU_DEV_EXTRA \
+        "
+    fi
+fi
 
 if [ "$1" = "microvm" ]; then
     QEMU_ARGS=$MICROVM_QEMU_ARGS